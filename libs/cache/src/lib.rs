@@ -0,0 +1,376 @@
+//! A read-through cache of Kanidm entries for services that look up the same accounts and
+//! groups at a high rate. Rather than expiring entries after a fixed time, the cache polls
+//! the server for the entries that have changed and only discards those, so entries that
+//! don't change can be served from the cache indefinitely.
+//!
+//! ```ignore
+//! let cache = Arc::new(KanidmCache::new(client));
+//! let _task = cache.spawn_refresh_task(Duration::from_secs(5));
+//! let group = cache.group_get("idm_admins").await?;
+//! ```
+
+#![deny(warnings)]
+#![warn(unused_extern_crates)]
+#![deny(clippy::todo)]
+#![deny(clippy::unimplemented)]
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::unreachable)]
+#![deny(clippy::await_holding_lock)]
+#![deny(clippy::needless_pass_by_value)]
+#![deny(clippy::trivially_copy_pass_by_ref)]
+
+#[macro_use]
+extern crate tracing;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use kanidm_client::{ClientError, KanidmClient};
+use kanidm_proto::v1::{ChangesResponse, Entry};
+use lru::LruCache;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+/// The default number of entries that are cached.
+pub const DEFAULT_CACHE_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum EntryKind {
+    Person,
+    Group,
+    ServiceAccount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct CacheKey {
+    kind: EntryKind,
+    /// The name, spn or uuid the entry was requested by.
+    id: String,
+}
+
+fn entry_uuid(entry: &Entry) -> Option<Uuid> {
+    entry
+        .attrs
+        .get("uuid")
+        .and_then(|values| values.first())
+        .and_then(|value| Uuid::parse_str(value).ok())
+}
+
+struct CacheState {
+    /// The cursor of the last changes that were applied. Until this is set, nothing can be
+    /// cached as we have no way to know if it changed.
+    cursor: Option<String>,
+    /// Incremented whenever entries are invalidated. An entry that was requested from the
+    /// server before an invalidation may already be stale, so it must not be cached.
+    generation: u64,
+    entries: LruCache<CacheKey, Entry>,
+    /// An entry can be cached under multiple keys, since it may be requested by name or uuid.
+    keys: BTreeMap<Uuid, BTreeSet<CacheKey>>,
+}
+
+impl CacheState {
+    fn new(size: NonZeroUsize) -> Self {
+        CacheState {
+            cursor: None,
+            generation: 0,
+            entries: LruCache::new(size),
+            keys: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &CacheKey) -> Option<Entry> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: CacheKey, entry: Entry, generation: u64) {
+        if self.cursor.is_none() || self.generation != generation {
+            return;
+        }
+
+        let Some(uuid) = entry_uuid(&entry) else {
+            warn!(?key, "Entry has no uuid, it will not be cached");
+            return;
+        };
+
+        // This is either an entry that was evicted, or the entry we are replacing.
+        if let Some((old_key, old_entry)) = self.entries.push(key.clone(), entry) {
+            if let Some(old_uuid) = entry_uuid(&old_entry) {
+                self.remove_key(old_uuid, &old_key);
+            }
+        }
+
+        self.keys.entry(uuid).or_default().insert(key);
+    }
+
+    fn remove_key(&mut self, uuid: Uuid, key: &CacheKey) {
+        if let Some(keys) = self.keys.get_mut(&uuid) {
+            keys.remove(key);
+            if keys.is_empty() {
+                self.keys.remove(&uuid);
+            }
+        }
+    }
+
+    fn invalidate(&mut self, uuid: &Uuid) {
+        if let Some(keys) = self.keys.remove(uuid) {
+            for key in keys.iter() {
+                self.entries.pop(key);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.keys.clear();
+        self.generation += 1;
+    }
+
+    fn apply(&mut self, changes: ChangesResponse) {
+        if changes.reset {
+            debug!("Change cursor was reset, discarding all cached entries");
+            self.clear();
+        } else if !changes.changed.is_empty() || !changes.removed.is_empty() {
+            for uuid in changes.changed.iter().chain(changes.removed.iter()) {
+                self.invalidate(uuid);
+            }
+            self.generation += 1;
+        }
+        self.cursor = Some(changes.cursor);
+    }
+}
+
+pub struct KanidmCache {
+    client: KanidmClient,
+    state: Mutex<CacheState>,
+}
+
+impl KanidmCache {
+    pub fn new(client: KanidmClient) -> Self {
+        Self::with_capacity(client, DEFAULT_CACHE_SIZE)
+    }
+
+    pub fn with_capacity(client: KanidmClient, size: usize) -> Self {
+        let size = NonZeroUsize::new(size).unwrap_or(NonZeroUsize::MIN);
+        KanidmCache {
+            client,
+            state: Mutex::new(CacheState::new(size)),
+        }
+    }
+
+    /// The client used by this cache, for requests that should not be cached.
+    pub fn client(&self) -> &KanidmClient {
+        &self.client
+    }
+
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        // The state is always consistent between calls, so a panic in another thread
+        // doesn't leave it invalid.
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub async fn person_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.get(EntryKind::Person, id).await
+    }
+
+    pub async fn group_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.get(EntryKind::Group, id).await
+    }
+
+    pub async fn service_account_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.get(EntryKind::ServiceAccount, id).await
+    }
+
+    async fn get(&self, kind: EntryKind, id: &str) -> Result<Option<Entry>, ClientError> {
+        let key = CacheKey {
+            kind,
+            id: id.to_string(),
+        };
+
+        let needs_cursor = {
+            let mut state = self.state();
+            if let Some(entry) = state.get(&key) {
+                trace!(?key, "Cache hit");
+                return Ok(Some(entry));
+            }
+            state.cursor.is_none()
+        };
+
+        if needs_cursor {
+            self.refresh().await?;
+        }
+
+        let generation = self.state().generation;
+
+        trace!(?key, "Cache miss");
+        let entry = match kind {
+            EntryKind::Person => self.client.idm_person_account_get(id).await?,
+            EntryKind::Group => self.client.idm_group_get(id).await?,
+            EntryKind::ServiceAccount => self.client.idm_service_account_get(id).await?,
+        };
+
+        // Entries that don't exist are not cached, since we won't be told when they
+        // are created.
+        if let Some(entry) = &entry {
+            self.state().insert(key, entry.clone(), generation);
+        }
+
+        Ok(entry)
+    }
+
+    /// Request the changes since the last refresh from the server, and discard any cached
+    /// entries that have changed.
+    pub async fn refresh(&self) -> Result<(), ClientError> {
+        let cursor = self.state().cursor.clone();
+        let changes = self.client.changes_get(cursor.as_deref()).await?;
+        self.state().apply(changes);
+        Ok(())
+    }
+
+    /// Discard all cached entries.
+    pub fn invalidate_all(&self) {
+        let mut state = self.state();
+        state.clear();
+        state.cursor = None;
+    }
+
+    /// Spawn a task that refreshes the cache at `interval`. If a refresh fails, all cached
+    /// entries are discarded since we can no longer know if they are valid.
+    pub fn spawn_refresh_task(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let cache = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(err) = cache.refresh().await {
+                    warn!(
+                        ?err,
+                        "Unable to refresh entry cache, discarding cached entries"
+                    );
+                    cache.invalidate_all();
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheKey, CacheState, EntryKind};
+    use kanidm_proto::v1::{ChangesResponse, Entry};
+    use std::num::NonZeroUsize;
+    use uuid::Uuid;
+
+    fn entry(uuid: Uuid, name: &str) -> Entry {
+        let mut entry = Entry::default();
+        entry
+            .attrs
+            .insert("uuid".to_string(), vec![uuid.to_string()]);
+        entry
+            .attrs
+            .insert("name".to_string(), vec![name.to_string()]);
+        entry
+    }
+
+    fn key(id: &str) -> CacheKey {
+        CacheKey {
+            kind: EntryKind::Group,
+            id: id.to_string(),
+        }
+    }
+
+    fn changes(cursor: &str, changed: Vec<Uuid>) -> ChangesResponse {
+        ChangesResponse {
+            cursor: cursor.to_string(),
+            reset: false,
+            changed,
+            removed: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_cache_invalidation() {
+        let mut state = CacheState::new(NonZeroUsize::new(8).unwrap());
+        let uuid_a = Uuid::new_v4();
+        let uuid_b = Uuid::new_v4();
+
+        // Nothing is cached until we have a cursor.
+        state.insert(key("a"), entry(uuid_a, "a"), state.generation);
+        assert!(state.get(&key("a")).is_none());
+
+        state.apply(ChangesResponse {
+            cursor: "1".to_string(),
+            reset: true,
+            ..Default::default()
+        });
+
+        // The same entry can be cached by name and uuid.
+        state.insert(key("a"), entry(uuid_a, "a"), state.generation);
+        state.insert(
+            key(&uuid_a.to_string()),
+            entry(uuid_a, "a"),
+            state.generation,
+        );
+        state.insert(key("b"), entry(uuid_b, "b"), state.generation);
+        assert!(state.get(&key("a")).is_some());
+        assert!(state.get(&key(&uuid_a.to_string())).is_some());
+
+        // Invalidating an entry removes it under every key, and leaves others alone.
+        state.apply(changes("2", vec![uuid_a]));
+        assert!(state.get(&key("a")).is_none());
+        assert!(state.get(&key(&uuid_a.to_string())).is_none());
+        assert!(state.get(&key("b")).is_some());
+        assert_eq!(state.cursor.as_deref(), Some("2"));
+
+        // An entry fetched before an invalidation is not cached.
+        let generation = state.generation;
+        state.apply(changes("3", vec![uuid_b]));
+        state.insert(key("a"), entry(uuid_a, "a"), generation);
+        assert!(state.get(&key("a")).is_none());
+
+        // But unrelated refreshes don't prevent caching.
+        let generation = state.generation;
+        state.apply(changes("4", Vec::new()));
+        state.insert(key("a"), entry(uuid_a, "a"), generation);
+        assert!(state.get(&key("a")).is_some());
+
+        // A reset discards everything.
+        state.apply(ChangesResponse {
+            cursor: "5".to_string(),
+            reset: true,
+            ..Default::default()
+        });
+        assert!(state.get(&key("a")).is_none());
+        assert!(state.keys.is_empty());
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let mut state = CacheState::new(NonZeroUsize::new(1).unwrap());
+        state.apply(changes("1", Vec::new()));
+
+        let uuid_a = Uuid::new_v4();
+        let uuid_b = Uuid::new_v4();
+
+        state.insert(key("a"), entry(uuid_a, "a"), state.generation);
+        state.insert(key("b"), entry(uuid_b, "b"), state.generation);
+
+        // The evicted entry is no longer tracked.
+        assert!(state.get(&key("a")).is_none());
+        assert!(!state.keys.contains_key(&uuid_a));
+        assert!(state.keys.contains_key(&uuid_b));
+
+        // A name that now refers to a different entry is tracked against the new uuid.
+        let uuid_c = Uuid::new_v4();
+        state.insert(key("b"), entry(uuid_c, "b"), state.generation);
+        assert!(!state.keys.contains_key(&uuid_b));
+        assert!(state.keys.contains_key(&uuid_c));
+    }
+}
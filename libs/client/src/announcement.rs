@@ -0,0 +1,85 @@
+use crate::{ClientError, KanidmClient};
+use kanidm_proto::constants::{
+    ATTR_ANNOUNCEMENT_CLI, ATTR_ANNOUNCEMENT_END, ATTR_ANNOUNCEMENT_MESSAGE,
+    ATTR_ANNOUNCEMENT_SEVERITY, ATTR_ANNOUNCEMENT_START, ATTR_NAME,
+};
+use kanidm_proto::internal::{Announcement, AnnouncementSeverity};
+use kanidm_proto::v1::Entry;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+impl KanidmClient {
+    /// The announcements that are currently active, most severe first.
+    pub async fn idm_announcement_active(&self) -> Result<Vec<Announcement>, ClientError> {
+        self.perform_get_request("/v1/announcement").await
+    }
+
+    pub async fn idm_announcement_list(&self) -> Result<Vec<Entry>, ClientError> {
+        self.perform_get_request("/v1/announcement/_all").await
+    }
+
+    pub async fn idm_announcement_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.perform_get_request(format!("/v1/announcement/{}", id).as_str())
+            .await
+    }
+
+    pub async fn idm_announcement_create(
+        &self,
+        name: &str,
+        message: &str,
+        severity: AnnouncementSeverity,
+        start: Option<OffsetDateTime>,
+        end: Option<OffsetDateTime>,
+        cli: bool,
+    ) -> Result<(), ClientError> {
+        let mut new_announcement = Entry::default();
+        new_announcement
+            .attrs
+            .insert(ATTR_NAME.to_string(), vec![name.to_string()]);
+        new_announcement.attrs.insert(
+            ATTR_ANNOUNCEMENT_MESSAGE.to_string(),
+            vec![message.to_string()],
+        );
+        new_announcement.attrs.insert(
+            ATTR_ANNOUNCEMENT_SEVERITY.to_string(),
+            vec![severity.to_string()],
+        );
+        for (attr, time) in [
+            (ATTR_ANNOUNCEMENT_START, start),
+            (ATTR_ANNOUNCEMENT_END, end),
+        ] {
+            if let Some(time) = time {
+                let time = time
+                    .format(&Rfc3339)
+                    .map_err(|_| ClientError::InvalidRequest(format!("Invalid time for {attr}")))?;
+                new_announcement.attrs.insert(attr.to_string(), vec![time]);
+            }
+        }
+        new_announcement
+            .attrs
+            .insert(ATTR_ANNOUNCEMENT_CLI.to_string(), vec![cli.to_string()]);
+        self.perform_post_request("/v1/announcement/_all", new_announcement)
+            .await
+    }
+
+    pub async fn idm_announcement_set_message(
+        &self,
+        id: &str,
+        message: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            format!(
+                "/v1/announcement/{}/_attr/{}",
+                id, ATTR_ANNOUNCEMENT_MESSAGE
+            )
+            .as_str(),
+            vec![message.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_announcement_delete(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_delete_request(format!("/v1/announcement/{}", id).as_str())
+            .await
+    }
+}
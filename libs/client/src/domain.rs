@@ -1,6 +1,9 @@
-use crate::{ClientError, KanidmClient};
-use kanidm_proto::constants::ATTR_DOMAIN_ALLOW_EASTER_EGGS;
-use kanidm_proto::internal::ImageValue;
+use crate::{decode_operation_error, ClientError, KanidmClient};
+use kanidm_proto::constants::{
+    ATTR_DOMAIN_ALLOW_EASTER_EGGS, ATTR_DOMAIN_LOGIN_BANNER, ATTR_DOMAIN_PRIMARY_COLOUR,
+    ATTR_DOMAIN_THEME, ATTR_WHITE_PAGES_RATE_LIMIT, ATTR_WHITE_PAGES_TRUSTED_NETWORK,
+};
+use kanidm_proto::internal::{ImageValue, UiTheme};
 use reqwest::multipart;
 
 impl KanidmClient {
@@ -17,6 +20,96 @@ impl KanidmClient {
         .await
     }
 
+    /// Set the default theme of the web interface for this domain
+    pub async fn idm_domain_set_theme(&self, theme: UiTheme) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("{}{}", "/v1/domain/_attr/", ATTR_DOMAIN_THEME),
+            vec![theme.to_string()],
+        )
+        .await
+    }
+
+    /// Set the message, such as a legal notice, that is shown on the login pages
+    pub async fn idm_domain_set_login_banner(&self, banner: &str) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("{}{}", "/v1/domain/_attr/", ATTR_DOMAIN_LOGIN_BANNER),
+            vec![banner.to_string()],
+        )
+        .await
+    }
+
+    /// Clear the message that is shown on the login pages
+    pub async fn idm_domain_remove_login_banner(&self) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!(
+            "{}{}",
+            "/v1/domain/_attr/", ATTR_DOMAIN_LOGIN_BANNER
+        ))
+        .await
+    }
+
+    /// Set the primary colour of the web interface, as a hex colour such as `#1f6feb`
+    pub async fn idm_domain_set_primary_colour(&self, colour: &str) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("{}{}", "/v1/domain/_attr/", ATTR_DOMAIN_PRIMARY_COLOUR),
+            vec![colour.to_string()],
+        )
+        .await
+    }
+
+    /// Clear the primary colour of the web interface, reverting to the default
+    pub async fn idm_domain_remove_primary_colour(&self) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!(
+            "{}{}",
+            "/v1/domain/_attr/", ATTR_DOMAIN_PRIMARY_COLOUR
+        ))
+        .await
+    }
+
+    /// Set the networks, in CIDR notation, that anonymous may search the white pages from
+    pub async fn idm_domain_set_white_pages_trusted_networks(
+        &self,
+        networks: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!(
+                "{}{}",
+                "/v1/domain/_attr/", ATTR_WHITE_PAGES_TRUSTED_NETWORK
+            ),
+            networks.to_vec(),
+        )
+        .await
+    }
+
+    /// Clear the white pages trusted networks, allowing anonymous to search from any network
+    pub async fn idm_domain_remove_white_pages_trusted_networks(&self) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!(
+            "{}{}",
+            "/v1/domain/_attr/", ATTR_WHITE_PAGES_TRUSTED_NETWORK
+        ))
+        .await
+    }
+
+    /// Set the number of white pages searches anonymous may make from one address each minute
+    pub async fn idm_domain_set_white_pages_rate_limit(
+        &self,
+        rate_limit: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("{}{}", "/v1/domain/_attr/", ATTR_WHITE_PAGES_RATE_LIMIT),
+            vec![rate_limit.to_string()],
+        )
+        .await
+    }
+
+    /// Clear the white pages rate limit
+    pub async fn idm_domain_remove_white_pages_rate_limit(&self) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!(
+            "{}{}",
+            "/v1/domain/_attr/", ATTR_WHITE_PAGES_RATE_LIMIT
+        ))
+        .await
+    }
+
     /// Add or update the domain logo/image
     pub async fn idm_domain_update_image(&self, image: ImageValue) -> Result<(), ClientError> {
         let file_content_type = image.filetype.as_content_type_str();
@@ -64,7 +157,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
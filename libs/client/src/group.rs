@@ -1,5 +1,8 @@
 use crate::{ClientError, KanidmClient};
-use kanidm_proto::v1::Entry;
+use kanidm_proto::v1::{
+    Entry, GroupMembershipRequestApprove, GroupMembershipRequestCreate, GroupMembershipRequests,
+};
+use uuid::Uuid;
 
 impl KanidmClient {
     pub async fn idm_group_search(&self, id: &str) -> Result<Vec<Entry>, ClientError> {
@@ -45,6 +48,26 @@ impl KanidmClient {
             .await
     }
 
+    pub async fn group_account_policy_authsession_idle_timeout_set(
+        &self,
+        id: &str,
+        timeout: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/authsession_idle_timeout", id),
+            vec![timeout.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_authsession_idle_timeout_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/authsession_idle_timeout", id))
+            .await
+    }
+
     pub async fn group_account_policy_credential_type_minimum_set(
         &self,
         id: &str,
@@ -80,6 +103,49 @@ impl KanidmClient {
         .await
     }
 
+    pub async fn group_account_policy_password_history_count_set(
+        &self,
+        id: &str,
+        count: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/auth_password_history_count", id),
+            vec![count.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_password_history_count_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!(
+            "/v1/group/{}/_attr/auth_password_history_count",
+            id
+        ))
+        .await
+    }
+
+    pub async fn group_account_policy_password_maximum_age_set(
+        &self,
+        id: &str,
+        age: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/auth_password_maximum_age", id),
+            vec![age.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_password_maximum_age_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/auth_password_maximum_age", id))
+            .await
+    }
+
     pub async fn group_account_policy_privilege_expiry_set(
         &self,
         id: &str,
@@ -178,6 +244,337 @@ impl KanidmClient {
         .await
     }
 
+    pub async fn group_account_policy_allow_unix_password(
+        &self,
+        id: &str,
+        allow: bool,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/allow_unix_password", id),
+            vec![allow.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_allow_api_tokens(
+        &self,
+        id: &str,
+        allow: bool,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/allow_api_tokens", id),
+            vec![allow.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_allow_email_link(
+        &self,
+        id: &str,
+        allow: bool,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/allow_email_link", id),
+            vec![allow.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_password_breach_check(
+        &self,
+        id: &str,
+        enable: bool,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/password_breach_check", id),
+            vec![enable.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_login_host_tag_set(
+        &self,
+        id: &str,
+        tags: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(&format!("/v1/group/{}/_attr/login_host_tag", id), tags)
+            .await
+    }
+
+    pub async fn group_account_policy_login_host_tag_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/login_host_tag", id))
+            .await
+    }
+
+    pub async fn group_account_policy_ssh_key_allowed_type_set(
+        &self,
+        id: &str,
+        key_types: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/ssh_key_allowed_type", id),
+            key_types,
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_ssh_key_allowed_type_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/ssh_key_allowed_type", id))
+            .await
+    }
+
+    pub async fn group_account_policy_ssh_key_rsa_minimum_bits_set(
+        &self,
+        id: &str,
+        bits: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/ssh_key_rsa_minimum_bits", id),
+            vec![bits.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_ssh_key_rsa_minimum_bits_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/ssh_key_rsa_minimum_bits", id))
+            .await
+    }
+
+    pub async fn group_account_policy_posix_default_shell_set(
+        &self,
+        id: &str,
+        shell: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/posix_default_shell", id),
+            vec![shell.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_posix_default_shell_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/posix_default_shell", id))
+            .await
+    }
+
+    pub async fn group_account_policy_posix_home_template_set(
+        &self,
+        id: &str,
+        template: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/posix_home_template", id),
+            vec![template.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_posix_home_template_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/posix_home_template", id))
+            .await
+    }
+
+    pub async fn group_account_policy_posix_gecos_format_set(
+        &self,
+        id: &str,
+        format: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/posix_gecos_format", id),
+            vec![format.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_posix_gecos_format_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/posix_gecos_format", id))
+            .await
+    }
+
+    pub async fn group_account_policy_posix_home_skeleton_set(
+        &self,
+        id: &str,
+        skeleton: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/posix_home_skeleton", id),
+            vec![skeleton.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_posix_home_skeleton_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/posix_home_skeleton", id))
+            .await
+    }
+
+    pub async fn group_account_policy_trusted_network_set(
+        &self,
+        id: &str,
+        networks: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/auth_trusted_network", id),
+            networks,
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_trusted_network_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/auth_trusted_network", id))
+            .await
+    }
+
+    pub async fn group_account_policy_credential_type_minimum_untrusted_network_set(
+        &self,
+        id: &str,
+        value: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!(
+                "/v1/group/{}/_attr/credential_type_minimum_untrusted_network",
+                id
+            ),
+            vec![value.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_credential_type_minimum_untrusted_network_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!(
+            "/v1/group/{}/_attr/credential_type_minimum_untrusted_network",
+            id
+        ))
+        .await
+    }
+
+    pub async fn group_account_policy_auth_lockout_threshold_set(
+        &self,
+        id: &str,
+        threshold: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/auth_lockout_threshold", id),
+            vec![threshold.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_auth_lockout_threshold_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/auth_lockout_threshold", id))
+            .await
+    }
+
+    pub async fn group_account_policy_auth_lockout_window_set(
+        &self,
+        id: &str,
+        seconds: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/auth_lockout_window", id),
+            vec![seconds.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_auth_lockout_window_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/auth_lockout_window", id))
+            .await
+    }
+
+    pub async fn group_account_policy_auth_lockout_duration_set(
+        &self,
+        id: &str,
+        seconds: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/auth_lockout_duration", id),
+            vec![seconds.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_auth_lockout_duration_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/auth_lockout_duration", id))
+            .await
+    }
+
+    pub async fn group_account_policy_account_lifetime_set(
+        &self,
+        id: &str,
+        seconds: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/account_lifetime", id),
+            vec![seconds.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_account_lifetime_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/account_lifetime", id))
+            .await
+    }
+
+    pub async fn group_account_policy_account_archive_delay_set(
+        &self,
+        id: &str,
+        seconds: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/account_archive_delay", id),
+            vec![seconds.to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_account_policy_account_archive_delay_reset(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!("/v1/group/{}/_attr/account_archive_delay", id))
+            .await
+    }
+
     pub async fn idm_group_purge_mail(&self, id: &str) -> Result<(), ClientError> {
         self.idm_group_purge_attr(id, "mail").await
     }
@@ -195,4 +592,73 @@ impl KanidmClient {
         self.perform_get_request(&format!("/v1/group/{}/_attr/mail", id))
             .await
     }
+
+    /// Nest this group beneath a parent group, so that it inherits the memberships of the parent.
+    pub async fn idm_group_set_parent(&self, id: &str, parent: &str) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/parent_group", id),
+            vec![parent],
+        )
+        .await
+    }
+
+    pub async fn idm_group_purge_parent(&self, id: &str) -> Result<(), ClientError> {
+        self.idm_group_purge_attr(id, "parent_group").await
+    }
+
+    pub async fn group_membership_request_enable(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/group/{}/_attr/membership_requestable", id),
+            vec!["true".to_string()],
+        )
+        .await
+    }
+
+    pub async fn group_membership_request_disable(&self, id: &str) -> Result<(), ClientError> {
+        self.idm_group_purge_attr(id, "membership_requestable")
+            .await
+    }
+
+    /// Request that the authenticated account joins this group, returning the id of the request.
+    pub async fn idm_group_membership_request(
+        &self,
+        id: &str,
+        justification: Option<String>,
+    ) -> Result<Uuid, ClientError> {
+        self.perform_post_request(
+            &format!("/v1/group/{}/_membership_request", id),
+            GroupMembershipRequestCreate { justification },
+        )
+        .await
+    }
+
+    pub async fn idm_group_membership_request_list(
+        &self,
+    ) -> Result<GroupMembershipRequests, ClientError> {
+        self.perform_get_request("/v1/group_membership_request")
+            .await
+    }
+
+    pub async fn idm_group_membership_request_approve(
+        &self,
+        request_id: Uuid,
+        valid_until: Option<time::OffsetDateTime>,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            &format!("/v1/group_membership_request/{}/_approve", request_id),
+            GroupMembershipRequestApprove { valid_until },
+        )
+        .await
+    }
+
+    pub async fn idm_group_membership_request_deny(
+        &self,
+        request_id: Uuid,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            &format!("/v1/group_membership_request/{}/_deny", request_id),
+            (),
+        )
+        .await
+    }
 }
@@ -0,0 +1,144 @@
+use crate::{ClientError, KanidmClient};
+use kanidm_proto::constants::{
+    ATTR_DESCRIPTION, ATTR_HBAC_DENY, ATTR_HBAC_HOST, ATTR_HBAC_HOST_GROUP, ATTR_HBAC_MEMBER,
+    ATTR_HBAC_SERVICE, ATTR_NAME,
+};
+use kanidm_proto::v1::Entry;
+
+impl KanidmClient {
+    pub async fn idm_hbac_rule_list(&self) -> Result<Vec<Entry>, ClientError> {
+        self.perform_get_request("/v1/hbac_rule").await
+    }
+
+    pub async fn idm_hbac_rule_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.perform_get_request(format!("/v1/hbac_rule/{}", id).as_str())
+            .await
+    }
+
+    pub async fn idm_hbac_rule_create(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        deny: bool,
+    ) -> Result<(), ClientError> {
+        let mut new_rule = Entry::default();
+        new_rule
+            .attrs
+            .insert(ATTR_NAME.to_string(), vec![name.to_string()]);
+        if let Some(description) = description {
+            new_rule
+                .attrs
+                .insert(ATTR_DESCRIPTION.to_string(), vec![description.to_string()]);
+        }
+        if deny {
+            new_rule
+                .attrs
+                .insert(ATTR_HBAC_DENY.to_string(), vec!["true".to_string()]);
+        }
+        self.perform_post_request("/v1/hbac_rule", new_rule).await
+    }
+
+    pub async fn idm_hbac_rule_delete(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_delete_request(format!("/v1/hbac_rule/{}", id).as_str())
+            .await
+    }
+
+    async fn idm_hbac_rule_add_attr(
+        &self,
+        id: &str,
+        attr: &str,
+        values: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/hbac_rule/{}/_attr/{}", id, attr).as_str(),
+            values,
+        )
+        .await
+    }
+
+    async fn idm_hbac_rule_remove_attr(
+        &self,
+        id: &str,
+        attr: &str,
+        values: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!("/v1/hbac_rule/{}/_attr/{}", id, attr).as_str(),
+            values,
+        )
+        .await
+    }
+
+    /// Add accounts or groups that the rule applies to.
+    pub async fn idm_hbac_rule_add_members(
+        &self,
+        id: &str,
+        members: &[String],
+    ) -> Result<(), ClientError> {
+        self.idm_hbac_rule_add_attr(id, ATTR_HBAC_MEMBER, members)
+            .await
+    }
+
+    pub async fn idm_hbac_rule_remove_members(
+        &self,
+        id: &str,
+        members: &[String],
+    ) -> Result<(), ClientError> {
+        self.idm_hbac_rule_remove_attr(id, ATTR_HBAC_MEMBER, members)
+            .await
+    }
+
+    pub async fn idm_hbac_rule_add_hosts(
+        &self,
+        id: &str,
+        hosts: &[String],
+    ) -> Result<(), ClientError> {
+        self.idm_hbac_rule_add_attr(id, ATTR_HBAC_HOST, hosts).await
+    }
+
+    pub async fn idm_hbac_rule_remove_hosts(
+        &self,
+        id: &str,
+        hosts: &[String],
+    ) -> Result<(), ClientError> {
+        self.idm_hbac_rule_remove_attr(id, ATTR_HBAC_HOST, hosts)
+            .await
+    }
+
+    /// Limit the rule to the machines of the named host groups.
+    pub async fn idm_hbac_rule_add_host_groups(
+        &self,
+        id: &str,
+        host_groups: &[String],
+    ) -> Result<(), ClientError> {
+        self.idm_hbac_rule_add_attr(id, ATTR_HBAC_HOST_GROUP, host_groups)
+            .await
+    }
+
+    pub async fn idm_hbac_rule_remove_host_groups(
+        &self,
+        id: &str,
+        host_groups: &[String],
+    ) -> Result<(), ClientError> {
+        self.idm_hbac_rule_remove_attr(id, ATTR_HBAC_HOST_GROUP, host_groups)
+            .await
+    }
+
+    pub async fn idm_hbac_rule_add_services(
+        &self,
+        id: &str,
+        services: &[String],
+    ) -> Result<(), ClientError> {
+        self.idm_hbac_rule_add_attr(id, ATTR_HBAC_SERVICE, services)
+            .await
+    }
+
+    pub async fn idm_hbac_rule_remove_services(
+        &self,
+        id: &str,
+        services: &[String],
+    ) -> Result<(), ClientError> {
+        self.idm_hbac_rule_remove_attr(id, ATTR_HBAC_SERVICE, services)
+            .await
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{ClientError, KanidmClient};
+use kanidm_proto::constants::{
+    ATTR_DESCRIPTION, ATTR_DISPLAYNAME, ATTR_HOST_KIOSK_CACHE_TIMEOUT, ATTR_HOST_KIOSK_GUEST_GROUP,
+    ATTR_HOST_KIOSK_MODE, ATTR_KERBEROS_SERVICE_PRINCIPAL, ATTR_NAME,
+};
+use kanidm_proto::v1::{
+    Entry, HostEnrollRequest, KerberosKeytab, KerberosTicket, KerberosTicketRequest,
+};
+
+impl KanidmClient {
+    pub async fn idm_host_list(&self) -> Result<Vec<Entry>, ClientError> {
+        self.perform_get_request("/v1/host").await
+    }
+
+    pub async fn idm_host_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.perform_get_request(format!("/v1/host/{}", id).as_str())
+            .await
+    }
+
+    pub async fn idm_host_create(
+        &self,
+        name: &str,
+        displayname: &str,
+        description: Option<&str>,
+    ) -> Result<(), ClientError> {
+        let mut new_host = Entry::default();
+        new_host
+            .attrs
+            .insert(ATTR_NAME.to_string(), vec![name.to_string()]);
+        new_host
+            .attrs
+            .insert(ATTR_DISPLAYNAME.to_string(), vec![displayname.to_string()]);
+        if let Some(description) = description {
+            new_host
+                .attrs
+                .insert(ATTR_DESCRIPTION.to_string(), vec![description.to_string()]);
+        }
+        self.perform_post_request("/v1/host", new_host).await
+    }
+
+    pub async fn idm_host_delete(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_delete_request(format!("/v1/host/{}", id).as_str())
+            .await
+    }
+
+    /// Issue a one time join token that allows a machine to enroll as the host. If no ttl is
+    /// given, the server default is used.
+    pub async fn idm_host_generate_join_token(
+        &self,
+        id: &str,
+        ttl: Option<u64>,
+    ) -> Result<String, ClientError> {
+        self.perform_post_request(format!("/v1/host/{}/_join_token", id).as_str(), ttl)
+            .await
+    }
+
+    /// Enroll this machine as a host, exchanging the join token for the credential of the
+    /// host. This does not require the client to be authenticated.
+    pub async fn idm_host_enroll(
+        &self,
+        name: &str,
+        join_token: &str,
+    ) -> Result<String, ClientError> {
+        let request = HostEnrollRequest {
+            name: name.to_string(),
+            join_token: join_token.to_string(),
+        };
+        self.perform_post_request("/v1/host/_enroll", request).await
+    }
+
+    /// Mark the host as a shared workstation, or return it to a normal host.
+    pub async fn idm_host_set_kiosk_mode(&self, id: &str, enable: bool) -> Result<(), ClientError> {
+        self.perform_put_request(
+            format!("/v1/host/{}/_attr/{}", id, ATTR_HOST_KIOSK_MODE).as_str(),
+            vec![enable.to_string()],
+        )
+        .await
+    }
+
+    /// Add kerberos service principal names, such as `nfs/files.example.com`, to the host.
+    pub async fn idm_host_add_kerberos_service_principals(
+        &self,
+        id: &str,
+        principals: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/host/{}/_attr/{}", id, ATTR_KERBEROS_SERVICE_PRINCIPAL).as_str(),
+            principals,
+        )
+        .await
+    }
+
+    pub async fn idm_host_remove_kerberos_service_principals(
+        &self,
+        id: &str,
+        principals: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!("/v1/host/{}/_attr/{}", id, ATTR_KERBEROS_SERVICE_PRINCIPAL).as_str(),
+            principals,
+        )
+        .await
+    }
+
+    /// Set how long, in seconds, a kiosk host may cache accounts for.
+    pub async fn idm_host_set_kiosk_cache_timeout(
+        &self,
+        id: &str,
+        timeout: u32,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            format!("/v1/host/{}/_attr/{}", id, ATTR_HOST_KIOSK_CACHE_TIMEOUT).as_str(),
+            vec![timeout.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_host_reset_kiosk_cache_timeout(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_delete_request(
+            format!("/v1/host/{}/_attr/{}", id, ATTR_HOST_KIOSK_CACHE_TIMEOUT).as_str(),
+        )
+        .await
+    }
+
+    /// Members of these groups are guests on the kiosk, and have their home directories
+    /// removed when they log out.
+    pub async fn idm_host_add_kiosk_guest_groups(
+        &self,
+        id: &str,
+        groups: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/host/{}/_attr/{}", id, ATTR_HOST_KIOSK_GUEST_GROUP).as_str(),
+            groups,
+        )
+        .await
+    }
+
+    pub async fn idm_host_remove_kiosk_guest_groups(
+        &self,
+        id: &str,
+        groups: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!("/v1/host/{}/_attr/{}", id, ATTR_HOST_KIOSK_GUEST_GROUP).as_str(),
+            groups,
+        )
+        .await
+    }
+
+    /// Exchange the credential of the authenticated host for a new one. The credential that
+    /// the client authenticated with is revoked.
+    pub async fn idm_host_rotate_credential(&self) -> Result<String, ClientError> {
+        self.perform_post_request("/v1/host/_rotate_credential", ())
+            .await
+    }
+
+    /// The keytab of the kerberos service principals of the authenticated host.
+    pub async fn idm_host_kerberos_keytab(&self) -> Result<KerberosKeytab, ClientError> {
+        self.perform_post_request("/v1/host/_kerberos_keytab", ())
+            .await
+    }
+
+    /// Request a kerberos service ticket on behalf of an account that has logged in to the
+    /// authenticated host.
+    pub async fn idm_host_kerberos_ticket(
+        &self,
+        account_id: &str,
+        service: &str,
+    ) -> Result<KerberosTicket, ClientError> {
+        let request = KerberosTicketRequest {
+            account_id: account_id.to_string(),
+            service: service.to_string(),
+        };
+        self.perform_post_request("/v1/host/_kerberos_ticket", request)
+            .await
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{ClientError, KanidmClient};
+use kanidm_proto::constants::{ATTR_DESCRIPTION, ATTR_HOST_GROUP_MEMBER, ATTR_HOST_TAG, ATTR_NAME};
+use kanidm_proto::v1::Entry;
+
+impl KanidmClient {
+    pub async fn idm_host_group_list(&self) -> Result<Vec<Entry>, ClientError> {
+        self.perform_get_request("/v1/host_group").await
+    }
+
+    pub async fn idm_host_group_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.perform_get_request(format!("/v1/host_group/{}", id).as_str())
+            .await
+    }
+
+    pub async fn idm_host_group_create(
+        &self,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<(), ClientError> {
+        let mut new_group = Entry::default();
+        new_group
+            .attrs
+            .insert(ATTR_NAME.to_string(), vec![name.to_string()]);
+        if let Some(description) = description {
+            new_group
+                .attrs
+                .insert(ATTR_DESCRIPTION.to_string(), vec![description.to_string()]);
+        }
+        self.perform_post_request("/v1/host_group", new_group).await
+    }
+
+    pub async fn idm_host_group_delete(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_delete_request(format!("/v1/host_group/{}", id).as_str())
+            .await
+    }
+
+    /// Add machine accounts to the host group.
+    pub async fn idm_host_group_add_members(
+        &self,
+        id: &str,
+        members: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/host_group/{}/_attr/{}", id, ATTR_HOST_GROUP_MEMBER).as_str(),
+            members,
+        )
+        .await
+    }
+
+    pub async fn idm_host_group_remove_members(
+        &self,
+        id: &str,
+        members: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!("/v1/host_group/{}/_attr/{}", id, ATTR_HOST_GROUP_MEMBER).as_str(),
+            members,
+        )
+        .await
+    }
+
+    /// Include all machine accounts that have any of these tags in the host group.
+    pub async fn idm_host_group_add_tags(
+        &self,
+        id: &str,
+        tags: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/host_group/{}/_attr/{}", id, ATTR_HOST_TAG).as_str(),
+            tags,
+        )
+        .await
+    }
+
+    pub async fn idm_host_group_remove_tags(
+        &self,
+        id: &str,
+        tags: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!("/v1/host_group/{}/_attr/{}", id, ATTR_HOST_TAG).as_str(),
+            tags,
+        )
+        .await
+    }
+
+    /// Add host tags to a machine account, so that it is included in host groups selecting
+    /// those tags.
+    pub async fn idm_machine_add_host_tags(
+        &self,
+        id: &str,
+        tags: &[String],
+    ) -> Result<(), ClientError> {
+        let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+        self.idm_service_account_add_attr(id, ATTR_HOST_TAG, &tags)
+            .await
+    }
+
+    pub async fn idm_machine_remove_host_tags(
+        &self,
+        id: &str,
+        tags: &[String],
+    ) -> Result<(), ClientError> {
+        let current = self
+            .idm_service_account_get_attr(id, ATTR_HOST_TAG)
+            .await?
+            .unwrap_or_default();
+
+        let remaining: Vec<&str> = current
+            .iter()
+            .filter(|tag| !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            .map(String::as_str)
+            .collect();
+
+        if remaining.is_empty() {
+            self.idm_service_account_purge_attr(id, ATTR_HOST_TAG).await
+        } else {
+            self.idm_service_account_set_attr(id, ATTR_HOST_TAG, &remaining)
+                .await
+        }
+    }
+}
@@ -31,8 +31,8 @@ pub use http;
 use kanidm_proto::constants::uri::V1_AUTH_VALID;
 use kanidm_proto::constants::{
     ATTR_DOMAIN_DISPLAY_NAME, ATTR_DOMAIN_LDAP_BASEDN, ATTR_DOMAIN_SSID, ATTR_ENTRY_MANAGED_BY,
-    ATTR_KEY_ACTION_REVOKE, ATTR_LDAP_ALLOW_UNIX_PW_BIND, ATTR_LDAP_MAX_QUERYABLE_ATTRS, ATTR_NAME,
-    CLIENT_TOKEN_CACHE, KOPID, KSESSIONID, KVERSION,
+    ATTR_KEY_ACTION_REVOKE, ATTR_LDAP_ALLOW_UNIX_PW_BIND, ATTR_LDAP_GROUP_COMPAT,
+    ATTR_LDAP_MAX_QUERYABLE_ATTRS, ATTR_NAME, CLIENT_TOKEN_CACHE, KOPID, KSESSIONID, KVERSION,
 };
 use kanidm_proto::internal::*;
 use kanidm_proto::v1::*;
@@ -50,14 +50,22 @@ use webauthn_rs_proto::{
     PublicKeyCredential, RegisterPublicKeyCredential, RequestChallengeResponse,
 };
 
+mod announcement;
 mod domain;
 mod group;
+mod hbac;
+mod host;
+mod hostgroup;
 mod oauth;
+mod oidc_upstream;
 mod person;
+mod saml2;
 mod scim;
 mod service_account;
+mod state;
 mod sync_account;
 mod system;
+mod webhook;
 
 const EXPECT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -82,6 +90,47 @@ pub enum ClientError {
     InvalidRequest(String),
 }
 
+impl ClientError {
+    /// The stable code of an error returned by the server, for branching on failures
+    /// without matching on operation errors that may change between releases.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            ClientError::Http(_, Some(err), _) => Some(ErrorCode::from(err)),
+            ClientError::Http(status, None, _) => match *status {
+                StatusCode::UNAUTHORIZED => Some(ErrorCode::NotAuthenticated),
+                StatusCode::FORBIDDEN => Some(ErrorCode::AccessDenied),
+                StatusCode::NOT_FOUND => Some(ErrorCode::NotFound),
+                StatusCode::CONFLICT => Some(ErrorCode::Conflict),
+                StatusCode::PRECONDITION_FAILED => Some(ErrorCode::PreconditionFailed),
+                StatusCode::BAD_REQUEST => Some(ErrorCode::InvalidRequest),
+                StatusCode::TOO_MANY_REQUESTS => Some(ErrorCode::RateLimited),
+                StatusCode::SERVICE_UNAVAILABLE => Some(ErrorCode::Unavailable),
+                _ => Some(ErrorCode::Unknown),
+            },
+            ClientError::Unauthorized | ClientError::AuthenticationFailed => {
+                Some(ErrorCode::NotAuthenticated)
+            }
+            ClientError::SessionExpired => Some(ErrorCode::SessionExpired),
+            _ => None,
+        }
+    }
+
+    /// Remediation advice for an error returned by the server.
+    pub fn hint(&self) -> Option<&'static str> {
+        self.error_code().and_then(|code| code.hint())
+    }
+}
+
+/// Decode the body of an error response. The server replies with a problem details document,
+/// but older servers send the bare operation error.
+pub(crate) async fn decode_operation_error(response: Response) -> Option<OperationError> {
+    let body = response.bytes().await.ok()?;
+    match serde_json::from_slice::<ProblemDetails>(&body) {
+        Ok(problem) => problem.operation_error,
+        Err(_) => serde_json::from_slice(&body).ok(),
+    }
+}
+
 /// Settings describing a single instance.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct KanidmClientConfigInstance {
@@ -738,7 +787,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -797,7 +846,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -875,7 +924,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -921,7 +970,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -963,7 +1012,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -1024,7 +1073,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -1066,7 +1115,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -1108,7 +1157,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -1213,6 +1262,39 @@ impl KanidmClient {
         r
     }
 
+    #[instrument(level = "debug", skip_all)]
+    pub async fn auth_step_email_link(&self, token: &str) -> Result<AuthResponse, ClientError> {
+        let auth_req = AuthRequest {
+            step: AuthStep::Cred(AuthCredential::EmailLink(token.to_string())),
+        };
+        let r: Result<AuthResponse, _> = self.perform_auth_post_request("/v1/auth", auth_req).await;
+
+        if let Ok(ar) = &r {
+            if let AuthState::Success(token) = &ar.state {
+                self.set_token(token.clone()).await;
+            };
+        };
+        r
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub async fn auth_step_jwt_assertion(
+        &self,
+        assertion: &str,
+    ) -> Result<AuthResponse, ClientError> {
+        let auth_req = AuthRequest {
+            step: AuthStep::Cred(AuthCredential::JwtAssertion(assertion.to_string())),
+        };
+        let r: Result<AuthResponse, _> = self.perform_auth_post_request("/v1/auth", auth_req).await;
+
+        if let Ok(ar) = &r {
+            if let AuthState::Success(token) = &ar.state {
+                self.set_token(token.clone()).await;
+            };
+        };
+        r
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub async fn auth_step_totp(&self, totp: u32) -> Result<AuthResponse, ClientError> {
         let auth_req = AuthRequest {
@@ -1292,6 +1374,47 @@ impl KanidmClient {
         }
     }
 
+    /// Request a challenge that must be signed by the break glass key.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn auth_break_glass_challenge(&self) -> Result<BreakGlassChallenge, ClientError> {
+        self.perform_post_request("/v1/auth/break_glass/_challenge", ())
+            .await
+    }
+
+    /// Submit the DER encoded signature of a break glass challenge. On success the session
+    /// token for the admin account is set on this client.
+    #[instrument(level = "debug", skip(self, signature))]
+    pub async fn auth_break_glass(&self, id: Uuid, signature: Vec<u8>) -> Result<(), ClientError> {
+        let response: BreakGlassResponse = self
+            .perform_post_request("/v1/auth/break_glass", BreakGlassRequest { id, signature })
+            .await?;
+
+        self.set_token(response.token).await;
+        Ok(())
+    }
+
+    /// Submit a recovery code of an emergency account. On success the session token for the
+    /// account is set on this client.
+    #[instrument(level = "debug", skip(self, code))]
+    pub async fn auth_break_glass_code(
+        &self,
+        account: &str,
+        code: &str,
+    ) -> Result<(), ClientError> {
+        let response: BreakGlassResponse = self
+            .perform_post_request(
+                "/v1/auth/break_glass/_code",
+                BreakGlassCodeRequest {
+                    account: account.to_string(),
+                    code: code.to_string(),
+                },
+            )
+            .await?;
+
+        self.set_token(response.token).await;
+        Ok(())
+    }
+
     #[instrument(level = "debug", skip(self, password))]
     pub async fn auth_simple_password(
         &self,
@@ -1424,6 +1547,34 @@ impl KanidmClient {
         }
     }
 
+    /// Begin authenticating a service account with a jwt assertion, returning the nonce that
+    /// the assertion must contain.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn auth_jwt_assertion_begin(&self, ident: &str) -> Result<String, ClientError> {
+        let mechs = self.auth_step_init(ident).await?;
+
+        if !mechs.contains(&AuthMech::JwtAssertion) {
+            debug!("Jwt assertion mech not presented");
+            return Err(ClientError::AuthenticationFailed);
+        }
+
+        let state = self.auth_step_begin(AuthMech::JwtAssertion).await?.pop();
+
+        match state {
+            Some(AuthAllowed::JwtAssertion(nonce)) => Ok(nonce),
+            _ => Err(ClientError::AuthenticationFailed),
+        }
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub async fn auth_jwt_assertion_complete(&self, assertion: &str) -> Result<(), ClientError> {
+        let r = self.auth_step_jwt_assertion(assertion).await?;
+        match r.state {
+            AuthState::Success(_token) => Ok(()),
+            _ => Err(ClientError::AuthenticationFailed),
+        }
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn auth_passkey_begin(
         &self,
@@ -1597,7 +1748,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -1611,6 +1762,30 @@ impl KanidmClient {
         Ok(Some(r.youare))
     }
 
+    /// Retrieve the entries that have changed since `cursor`. If `reset` is set in the
+    /// response, the changes could not be determined and any cached entries must be discarded.
+    pub async fn changes_get(&self, cursor: Option<&str>) -> Result<ChangesResponse, ClientError> {
+        let query = ChangesQuery {
+            cursor: cursor.map(str::to_string),
+        };
+        self.perform_get_request_query("/v1/changes", Some(query))
+            .await
+    }
+
+    /// Retrieve the internal representation of an entry with its change metadata. This
+    /// includes recycled entries and tombstones, and requires system administrator rights.
+    pub async fn entry_inspect(&self, id: &str) -> Result<EntryInspectResponse, ClientError> {
+        self.perform_get_request(format!("/v1/debug/entry/{}", id).as_str())
+            .await
+    }
+
+    /// Retrieve the change history of an entry from the audit log, along with its change
+    /// metadata. This requires system administrator rights.
+    pub async fn entry_history(&self, id: &str) -> Result<EntryHistoryResponse, ClientError> {
+        self.perform_get_request(format!("/v1/debug/entry/{}/_history", id).as_str())
+            .await
+    }
+
     // Raw DB actions
     pub async fn search(&self, filter: Filter) -> Result<Vec<Entry>, ClientError> {
         let sr = SearchRequest { filter };
@@ -1623,6 +1798,17 @@ impl KanidmClient {
         self.perform_post_request("/v1/raw/create", c).await
     }
 
+    /// Import a batch of entries. Entries that could not be imported are returned in the
+    /// response, identified by their position in the batch.
+    pub async fn bulk_import(
+        &self,
+        phase: BulkImportPhase,
+        entries: Vec<Entry>,
+    ) -> Result<BulkImportResponse, ClientError> {
+        let r = BulkImportRequest { phase, entries };
+        self.perform_post_request("/v1/import", r).await
+    }
+
     pub async fn modify(&self, filter: Filter, modlist: ModifyList) -> Result<(), ClientError> {
         let mr = ModifyRequest { filter, modlist };
         self.perform_post_request("/v1/raw/modify", mr).await
@@ -1633,6 +1819,31 @@ impl KanidmClient {
         self.perform_post_request("/v1/raw/delete", dr).await
     }
 
+    /// Check a create without committing it, returning the entries as they would be created.
+    pub async fn create_dry_run(&self, entries: Vec<Entry>) -> Result<DryRunResponse, ClientError> {
+        let c = CreateRequest { entries };
+        self.perform_post_request("/v1/raw/create?dry_run=true", c)
+            .await
+    }
+
+    /// Check a modify without committing it, returning the entries as they would be modified.
+    pub async fn modify_dry_run(
+        &self,
+        filter: Filter,
+        modlist: ModifyList,
+    ) -> Result<DryRunResponse, ClientError> {
+        let mr = ModifyRequest { filter, modlist };
+        self.perform_post_request("/v1/raw/modify?dry_run=true", mr)
+            .await
+    }
+
+    /// Check a delete without committing it, returning the entries that would be deleted.
+    pub async fn delete_dry_run(&self, filter: Filter) -> Result<DryRunResponse, ClientError> {
+        let dr = DeleteRequest { filter };
+        self.perform_post_request("/v1/raw/delete?dry_run=true", dr)
+            .await
+    }
+
     // === idm actions here ==
 
     // ===== GROUPS
@@ -1704,6 +1915,22 @@ impl KanidmClient {
             .await
     }
 
+    /// Add members to a group until a point in time, after which their membership lapses.
+    /// If a member is already time-bound, their membership is extended or shortened to this time.
+    pub async fn idm_group_add_temporary_members(
+        &self,
+        id: &str,
+        members: &[&str],
+        valid_until: time::OffsetDateTime,
+    ) -> Result<(), ClientError> {
+        let request = GroupTemporaryMembers {
+            members: members.iter().map(|v| (*v).to_string()).collect(),
+            valid_until,
+        };
+        self.perform_post_request(&format!("/v1/group/{}/_temporary_members", id), request)
+            .await
+    }
+
     pub async fn idm_group_remove_members(
         &self,
         group: &str,
@@ -1753,6 +1980,15 @@ impl KanidmClient {
             .await
     }
 
+    /// The account policy that applies to an account.
+    pub async fn idm_account_get_account_policy(
+        &self,
+        id: &str,
+    ) -> Result<EffectiveAccountPolicy, ClientError> {
+        self.perform_get_request(&format!("/v1/account/{}/_account_policy", id))
+            .await
+    }
+
     // == new credential update session code.
     #[instrument(level = "debug", skip(self))]
     pub async fn idm_person_account_credential_update_intent(
@@ -1772,6 +2008,49 @@ impl KanidmClient {
         }
     }
 
+    /// Create a credential reset token for a person that may be used up to `max_uses` times
+    /// before it expires. If `send_mail` is set, the token is mailed to the person.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn idm_person_account_credential_create_reset_token(
+        &self,
+        id: &str,
+        ttl: Option<u64>,
+        max_uses: Option<u32>,
+        send_mail: bool,
+    ) -> Result<CUIntentToken, ClientError> {
+        self.perform_post_request(
+            &format!("/v1/person/{}/_credential/_update_intent", id),
+            CUIntentTokenRequest {
+                ttl,
+                max_uses,
+                send_mail,
+            },
+        )
+        .await
+    }
+
+    /// List the credential reset tokens of a person that have not yet expired.
+    pub async fn idm_person_account_credential_list_reset_tokens(
+        &self,
+        id: &str,
+    ) -> Result<Vec<CUIntentTokenInfo>, ClientError> {
+        self.perform_get_request(&format!("/v1/person/{}/_credential/_intents", id))
+            .await
+    }
+
+    /// Revoke a credential reset token of a person by its reference.
+    pub async fn idm_person_account_credential_revoke_reset_token(
+        &self,
+        id: &str,
+        token_ref: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(&format!(
+            "/v1/person/{}/_credential/_intents/{}",
+            id, token_ref
+        ))
+        .await
+    }
+
     pub async fn idm_account_credential_update_begin(
         &self,
         id: &str,
@@ -1797,6 +2076,23 @@ impl KanidmClient {
             .await
     }
 
+    pub async fn idm_account_credential_update_suggest_passphrase(
+        &self,
+        session_token: &CUSessionToken,
+    ) -> Result<String, ClientError> {
+        self.perform_simple_post_request("/v1/credential/_passphrase", &session_token)
+            .await
+    }
+
+    pub async fn idm_account_credential_update_check_password(
+        &self,
+        session_token: &CUSessionToken,
+        pw: &str,
+    ) -> Result<CUPasswordCheck, ClientError> {
+        self.perform_simple_post_request("/v1/credential/_check_password", &(pw, &session_token))
+            .await
+    }
+
     pub async fn idm_account_credential_update_set_password(
         &self,
         session_token: &CUSessionToken,
@@ -1995,6 +2291,59 @@ impl KanidmClient {
             .await
     }
 
+    /// The PEM encoded certificate authority that issues client certificates for EAP-TLS.
+    pub async fn idm_radius_ca_certificate_get(&self) -> Result<String, ClientError> {
+        self.perform_get_request("/v1/radius/_ca_certificate").await
+    }
+
+    /// Request a short lived ssh user certificate for an OpenSSH format public key. Returns the
+    /// certificate in the OpenSSH format.
+    pub async fn idm_ssh_certificate_request(
+        &self,
+        public_key: &str,
+    ) -> Result<String, ClientError> {
+        self.perform_post_request("/v1/self/_ssh_certificate", public_key.to_string())
+            .await
+    }
+
+    /// The public keys of the certificate authorities that issue ssh user certificates.
+    pub async fn idm_ssh_ca_public_keys_get(&self) -> Result<Vec<String>, ClientError> {
+        self.perform_get_request("/v1/ssh/_ca_public_keys").await
+    }
+
+    /// List the application passwords of the authenticated person.
+    pub async fn idm_self_application_password_list(
+        &self,
+    ) -> Result<Vec<ApplicationPassword>, ClientError> {
+        self.perform_get_request("/v1/self/_application_passwords")
+            .await
+    }
+
+    /// Generate an application password for the authenticated person. Returns the password,
+    /// which can't be retrieved again.
+    pub async fn idm_self_application_password_generate(
+        &self,
+        application: &str,
+        label: &str,
+    ) -> Result<String, ClientError> {
+        let req = ApplicationPasswordRequest {
+            application: application.to_string(),
+            label: label.to_string(),
+        };
+        self.perform_post_request("/v1/self/_application_passwords", req)
+            .await
+    }
+
+    pub async fn idm_self_application_password_revoke(
+        &self,
+        password_id: Uuid,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(
+            format!("/v1/self/_application_passwords/{}", password_id).as_str(),
+        )
+        .await
+    }
+
     pub async fn idm_account_unix_cred_verify(
         &self,
         id: &str,
@@ -2073,6 +2422,15 @@ impl KanidmClient {
         .await
     }
 
+    /// Enables or disables matching of nested group members in LDAP member filters
+    pub async fn idm_domain_set_ldap_group_compat(&self, enable: bool) -> Result<(), ClientError> {
+        self.perform_put_request(
+            &format!("/v1/domain/_attr/{}", ATTR_LDAP_GROUP_COMPAT),
+            vec![enable.to_string()],
+        )
+        .await
+    }
+
     pub async fn idm_domain_get_ssid(&self) -> Result<String, ClientError> {
         self.perform_get_request(&format!("/v1/domain/_attr/{}", ATTR_DOMAIN_SSID))
             .await
@@ -1,14 +1,26 @@
-use crate::{ClientError, KanidmClient};
+use crate::{decode_operation_error, ClientError, KanidmClient};
 use kanidm_proto::attribute::Attribute;
 use kanidm_proto::constants::{
-    ATTR_DISPLAYNAME, ATTR_ES256_PRIVATE_KEY_DER, ATTR_NAME,
-    ATTR_OAUTH2_ALLOW_INSECURE_CLIENT_DISABLE_PKCE, ATTR_OAUTH2_ALLOW_LOCALHOST_REDIRECT,
-    ATTR_OAUTH2_JWT_LEGACY_CRYPTO_ENABLE, ATTR_OAUTH2_PREFER_SHORT_USERNAME,
-    ATTR_OAUTH2_RS_BASIC_SECRET, ATTR_OAUTH2_RS_ORIGIN, ATTR_OAUTH2_RS_ORIGIN_LANDING,
-    ATTR_OAUTH2_RS_TOKEN_KEY, ATTR_OAUTH2_STRICT_REDIRECT_URI, ATTR_RS256_PRIVATE_KEY_DER,
+    ATTR_DISPLAYNAME, ATTR_ENTRY_MANAGED_BY, ATTR_ES256_PRIVATE_KEY_DER, ATTR_KEY_ACTION_REVOKE,
+    ATTR_KEY_ACTION_ROTATE, ATTR_NAME, ATTR_OAUTH2_ALLOWED_GRANT_TYPES,
+    ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES, ATTR_OAUTH2_ALLOW_INSECURE_CLIENT_DISABLE_PKCE,
+    ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE, ATTR_OAUTH2_ALLOW_LOCALHOST_REDIRECT,
+    ATTR_OAUTH2_JWT_LEGACY_CRYPTO_ENABLE, ATTR_OAUTH2_JWT_SIGN_ALG,
+    ATTR_OAUTH2_PREFER_SHORT_USERNAME, ATTR_OAUTH2_REDIRECT_URI_MODE,
+    ATTR_OAUTH2_RS_ASSIGNED_MEMBER, ATTR_OAUTH2_RS_BASIC_SECRET,
+    ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY, ATTR_OAUTH2_RS_BASIC_SECRET_LAST_USED,
+    ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS, ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY,
+    ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED, ATTR_OAUTH2_RS_COMPUTED_CLAIM,
+    ATTR_OAUTH2_RS_ORIGIN, ATTR_OAUTH2_RS_ORIGIN_LANDING, ATTR_OAUTH2_RS_SCOPE_DESCRIPTION,
+    ATTR_OAUTH2_RS_TOKEN_KEY, ATTR_OAUTH2_STRICT_REDIRECT_URI, ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE,
+    ATTR_RS256_PRIVATE_KEY_DER,
 };
-use kanidm_proto::internal::{ImageValue, Oauth2ClaimMapJoin};
-use kanidm_proto::v1::Entry;
+use kanidm_proto::internal::{
+    ImageValue, Oauth2BasicSecretRotateRequest, Oauth2ClaimMapJoin, Oauth2ComputedClaim,
+    Oauth2GrantType, Oauth2JwtSignAlg, Oauth2RedirectUriMode, Oauth2ResponseType,
+    Oauth2ScopeDescription,
+};
+use kanidm_proto::v1::{Entry, Oauth2SessionStatus};
 use reqwest::multipart;
 use std::collections::BTreeMap;
 use url::Url;
@@ -70,6 +82,31 @@ impl KanidmClient {
             .await
     }
 
+    pub async fn idm_oauth2_rs_native_create(
+        &self,
+        name: &str,
+        displayname: &str,
+        origin: &str,
+    ) -> Result<(), ClientError> {
+        let mut new_oauth2_rs = Entry::default();
+        new_oauth2_rs
+            .attrs
+            .insert(ATTR_NAME.to_string(), vec![name.to_string()]);
+        new_oauth2_rs
+            .attrs
+            .insert(ATTR_DISPLAYNAME.to_string(), vec![displayname.to_string()]);
+        new_oauth2_rs.attrs.insert(
+            ATTR_OAUTH2_RS_ORIGIN_LANDING.to_string(),
+            vec![origin.to_string()],
+        );
+        new_oauth2_rs.attrs.insert(
+            ATTR_OAUTH2_STRICT_REDIRECT_URI.to_string(),
+            vec!["true".to_string()],
+        );
+        self.perform_post_request("/v1/oauth2/_native", new_oauth2_rs)
+            .await
+    }
+
     // TODO: the "id" here is actually the *name* not the uuid of the entry...
     pub async fn idm_oauth2_rs_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
         self.perform_get_request(format!("/v1/oauth2/{}", id).as_str())
@@ -84,6 +121,83 @@ impl KanidmClient {
             .await
     }
 
+    /// Replace the basic secret of a client. The previous secret remains valid for
+    /// `previous_secret_validity` seconds, or the server default if this is not set.
+    pub async fn idm_oauth2_rs_rotate_basic_secret(
+        &self,
+        id: &str,
+        previous_secret_validity: Option<u64>,
+    ) -> Result<(), ClientError> {
+        let rotate_req = Oauth2BasicSecretRotateRequest {
+            previous_secret_validity,
+        };
+        self.perform_post_request(
+            format!("/v1/oauth2/{}/_basic_secret/_rotate", id).as_str(),
+            rotate_req,
+        )
+        .await
+    }
+
+    /// Remove the previous basic secret of a client, so that it is no longer valid even if
+    /// it has not yet expired.
+    pub async fn idm_oauth2_rs_remove_previous_basic_secret(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        let mut update_oauth2_rs = Entry {
+            attrs: BTreeMap::new(),
+        };
+        for attr in [
+            ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS,
+            ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY,
+            ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED,
+        ] {
+            update_oauth2_rs.attrs.insert(attr.to_string(), Vec::new());
+        }
+        self.perform_patch_request(format!("/v1/oauth2/{}", id).as_str(), update_oauth2_rs)
+            .await
+    }
+
+    /// Set the time after which the basic secret of a client is no longer valid. If `expiry`
+    /// is `None` the secret does not expire.
+    pub async fn idm_oauth2_rs_set_basic_secret_expiry(
+        &self,
+        id: &str,
+        expiry: Option<&str>,
+    ) -> Result<(), ClientError> {
+        match expiry {
+            Some(expiry) => {
+                let mut update_oauth2_rs = Entry {
+                    attrs: BTreeMap::new(),
+                };
+                update_oauth2_rs.attrs.insert(
+                    ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY.to_string(),
+                    vec![expiry.to_string()],
+                );
+                self.perform_patch_request(format!("/v1/oauth2/{}", id).as_str(), update_oauth2_rs)
+                    .await
+            }
+            None => {
+                self.perform_delete_request(
+                    format!(
+                        "/v1/oauth2/{}/_attr/{}",
+                        id, ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY
+                    )
+                    .as_str(),
+                )
+                .await
+            }
+        }
+    }
+
+    pub async fn idm_oauth2_rs_list_sessions(
+        &self,
+        id: &str,
+    ) -> Result<Vec<Oauth2SessionStatus>, ClientError> {
+        self.perform_get_request(format!("/v1/oauth2/{}/_session", id).as_str())
+            .await
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn idm_oauth2_rs_update(
         &self,
@@ -117,9 +231,17 @@ impl KanidmClient {
             );
         }
         if reset_secret {
-            update_oauth2_rs
-                .attrs
-                .insert(ATTR_OAUTH2_RS_BASIC_SECRET.to_string(), Vec::new());
+            // An immediate reset also ends the validity of any previous secret.
+            for attr in [
+                ATTR_OAUTH2_RS_BASIC_SECRET,
+                ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY,
+                ATTR_OAUTH2_RS_BASIC_SECRET_LAST_USED,
+                ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS,
+                ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY,
+                ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED,
+            ] {
+                update_oauth2_rs.attrs.insert(attr.to_string(), Vec::new());
+            }
         }
         if reset_token_key {
             update_oauth2_rs
@@ -246,7 +368,7 @@ impl KanidmClient {
             unexpect => {
                 return Err(ClientError::Http(
                     unexpect,
-                    response.json().await.ok(),
+                    decode_operation_error(response).await,
                     opid,
                 ))
             }
@@ -281,6 +403,36 @@ impl KanidmClient {
             .await
     }
 
+    pub async fn idm_oauth2_rs_enable_refresh_token_rotation(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        let mut update_oauth2_rs = Entry {
+            attrs: BTreeMap::new(),
+        };
+        update_oauth2_rs.attrs.insert(
+            ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE.to_string(),
+            Vec::new(),
+        );
+        self.perform_patch_request(format!("/v1/oauth2/{}", id).as_str(), update_oauth2_rs)
+            .await
+    }
+
+    pub async fn idm_oauth2_rs_disable_refresh_token_rotation(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        let mut update_oauth2_rs = Entry {
+            attrs: BTreeMap::new(),
+        };
+        update_oauth2_rs.attrs.insert(
+            ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE.to_string(),
+            vec!["true".to_string()],
+        );
+        self.perform_patch_request(format!("/v1/oauth2/{}", id).as_str(), update_oauth2_rs)
+            .await
+    }
+
     pub async fn idm_oauth2_rs_enable_legacy_crypto(&self, id: &str) -> Result<(), ClientError> {
         let mut update_oauth2_rs = Entry {
             attrs: BTreeMap::new(),
@@ -389,6 +541,64 @@ impl KanidmClient {
             .await
     }
 
+    pub async fn idm_oauth2_rs_set_redirect_uri_mode(
+        &self,
+        id: &str,
+        mode: Oauth2RedirectUriMode,
+    ) -> Result<(), ClientError> {
+        let mut update_oauth2_rs = Entry {
+            attrs: BTreeMap::new(),
+        };
+        update_oauth2_rs.attrs.insert(
+            ATTR_OAUTH2_REDIRECT_URI_MODE.to_string(),
+            vec![mode.to_string()],
+        );
+        self.perform_patch_request(format!("/v1/oauth2/{}", id).as_str(), update_oauth2_rs)
+            .await
+    }
+
+    pub async fn idm_oauth2_rs_set_jwt_sign_alg(
+        &self,
+        id: &str,
+        sign_alg: Oauth2JwtSignAlg,
+    ) -> Result<(), ClientError> {
+        let mut update_oauth2_rs = Entry {
+            attrs: BTreeMap::new(),
+        };
+        update_oauth2_rs.attrs.insert(
+            ATTR_OAUTH2_JWT_SIGN_ALG.to_string(),
+            vec![sign_alg.to_string()],
+        );
+        self.perform_patch_request(format!("/v1/oauth2/{}", id).as_str(), update_oauth2_rs)
+            .await
+    }
+
+    /// Rotate the signing keys of this client. The new key is used from `rotate_at`, which
+    /// is an RFC3339 formatted timestamp.
+    pub async fn idm_oauth2_rs_rotate_keys(
+        &self,
+        id: &str,
+        rotate_at: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/oauth2/{}/_attr/{}", id, ATTR_KEY_ACTION_ROTATE).as_str(),
+            vec![rotate_at.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_rs_revoke_key(
+        &self,
+        id: &str,
+        key_id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/oauth2/{}/_attr/{}", id, ATTR_KEY_ACTION_REVOKE).as_str(),
+            vec![key_id.to_string()],
+        )
+        .await
+    }
+
     pub async fn idm_oauth2_rs_update_claim_map(
         &self,
         id: &str,
@@ -457,6 +667,249 @@ impl KanidmClient {
         .await
     }
 
+    pub async fn idm_oauth2_client_add_token_exchange_audience(
+        &self,
+        id: &str,
+        audience: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!(
+                "/v1/oauth2/{}/_attr/{}",
+                id, ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE
+            )
+            .as_str(),
+            &[audience],
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_client_remove_token_exchange_audience(
+        &self,
+        id: &str,
+        audience: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!(
+                "/v1/oauth2/{}/_attr/{}",
+                id, ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE
+            )
+            .as_str(),
+            &[audience],
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_client_add_assigned_member(
+        &self,
+        id: &str,
+        member: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/oauth2/{}/_attr/{}", id, ATTR_OAUTH2_RS_ASSIGNED_MEMBER).as_str(),
+            &[member],
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_client_remove_assigned_member(
+        &self,
+        id: &str,
+        member: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!("/v1/oauth2/{}/_attr/{}", id, ATTR_OAUTH2_RS_ASSIGNED_MEMBER).as_str(),
+            &[member],
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_client_add_allowed_grant_type(
+        &self,
+        id: &str,
+        grant_type: Oauth2GrantType,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!(
+                "/v1/oauth2/{}/_attr/{}",
+                id, ATTR_OAUTH2_ALLOWED_GRANT_TYPES
+            )
+            .as_str(),
+            &[grant_type.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_client_remove_allowed_grant_type(
+        &self,
+        id: &str,
+        grant_type: Oauth2GrantType,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!(
+                "/v1/oauth2/{}/_attr/{}",
+                id, ATTR_OAUTH2_ALLOWED_GRANT_TYPES
+            )
+            .as_str(),
+            &[grant_type.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_client_add_allowed_response_type(
+        &self,
+        id: &str,
+        response_type: Oauth2ResponseType,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!(
+                "/v1/oauth2/{}/_attr/{}",
+                id, ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES
+            )
+            .as_str(),
+            &[response_type.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_client_remove_allowed_response_type(
+        &self,
+        id: &str,
+        response_type: Oauth2ResponseType,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!(
+                "/v1/oauth2/{}/_attr/{}",
+                id, ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES
+            )
+            .as_str(),
+            &[response_type.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_client_add_scope_description(
+        &self,
+        id: &str,
+        scope_description: &Oauth2ScopeDescription,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!(
+                "/v1/oauth2/{}/_attr/{}",
+                id, ATTR_OAUTH2_RS_SCOPE_DESCRIPTION
+            )
+            .as_str(),
+            &[scope_description.to_string()],
+        )
+        .await
+    }
+
+    /// Remove the descriptions of `scope`. If `language` is `None` then the description in every
+    /// language is removed.
+    pub async fn idm_oauth2_client_remove_scope_description(
+        &self,
+        id: &str,
+        scope: &str,
+        language: Option<&str>,
+    ) -> Result<(), ClientError> {
+        let Some(entry) = self.idm_oauth2_rs_get(id).await? else {
+            return Err(ClientError::EmptyResponse);
+        };
+
+        let language = language.map(str::to_ascii_lowercase);
+        let values: Vec<String> = entry
+            .attrs
+            .get(ATTR_OAUTH2_RS_SCOPE_DESCRIPTION)
+            .into_iter()
+            .flatten()
+            .filter(|value| {
+                value
+                    .parse::<Oauth2ScopeDescription>()
+                    .map(|desc| {
+                        desc.scope == scope && (language.is_none() || desc.language == language)
+                    })
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        self.perform_delete_request_with_body(
+            format!(
+                "/v1/oauth2/{}/_attr/{}",
+                id, ATTR_OAUTH2_RS_SCOPE_DESCRIPTION
+            )
+            .as_str(),
+            &values,
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_client_add_computed_claim(
+        &self,
+        id: &str,
+        computed_claim: &Oauth2ComputedClaim,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/oauth2/{}/_attr/{}", id, ATTR_OAUTH2_RS_COMPUTED_CLAIM).as_str(),
+            &[computed_claim.to_string()],
+        )
+        .await
+    }
+
+    /// Remove every computed claim that emits `claim`.
+    pub async fn idm_oauth2_client_remove_computed_claim(
+        &self,
+        id: &str,
+        claim: &str,
+    ) -> Result<(), ClientError> {
+        let Some(entry) = self.idm_oauth2_rs_get(id).await? else {
+            return Err(ClientError::EmptyResponse);
+        };
+
+        let values: Vec<String> = entry
+            .attrs
+            .get(ATTR_OAUTH2_RS_COMPUTED_CLAIM)
+            .into_iter()
+            .flatten()
+            .filter(|value| {
+                value
+                    .split_once('=')
+                    .map(|(name, _)| name.trim() == claim)
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        self.perform_delete_request_with_body(
+            format!("/v1/oauth2/{}/_attr/{}", id, ATTR_OAUTH2_RS_COMPUTED_CLAIM).as_str(),
+            &values,
+        )
+        .await
+    }
+
+    pub async fn idm_oauth2_rs_set_entry_managed_by(
+        &self,
+        id: &str,
+        entry_manager: &str,
+    ) -> Result<(), ClientError> {
+        let mut update_oauth2_rs = Entry {
+            attrs: BTreeMap::new(),
+        };
+        update_oauth2_rs.attrs.insert(
+            ATTR_ENTRY_MANAGED_BY.to_string(),
+            vec![entry_manager.to_string()],
+        );
+        self.perform_patch_request(format!("/v1/oauth2/{}", id).as_str(), update_oauth2_rs)
+            .await
+    }
+
     pub async fn idm_oauth2_client_device_flow_update(
         &self,
         id: &str,
@@ -0,0 +1,109 @@
+use crate::{ClientError, KanidmClient};
+use kanidm_proto::constants::{
+    ATTR_DISPLAYNAME, ATTR_NAME, ATTR_OIDC_UPSTREAM_AUTHORISATION_ENDPOINT,
+    ATTR_OIDC_UPSTREAM_CLIENT_ID, ATTR_OIDC_UPSTREAM_CLIENT_SECRET, ATTR_OIDC_UPSTREAM_ISSUER,
+    ATTR_OIDC_UPSTREAM_JWKS_URI, ATTR_OIDC_UPSTREAM_TOKEN_ENDPOINT,
+};
+use kanidm_proto::v1::Entry;
+use serde::Deserialize;
+use url::Url;
+
+/// The parts of the discovery document of an upstream provider that we need. Providers
+/// publish far more than this, and not always in the forms we'd expect.
+#[derive(Debug, Deserialize)]
+struct OidcUpstreamDiscovery {
+    issuer: Url,
+    authorization_endpoint: Url,
+    token_endpoint: Url,
+    jwks_uri: Url,
+}
+
+impl KanidmClient {
+    pub async fn idm_oidc_upstream_list(&self) -> Result<Vec<Entry>, ClientError> {
+        self.perform_get_request("/v1/oidc_upstream").await
+    }
+
+    pub async fn idm_oidc_upstream_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.perform_get_request(format!("/v1/oidc_upstream/{}", id).as_str())
+            .await
+    }
+
+    /// Create an upstream provider. The endpoints of the provider are found from the discovery
+    /// document that the issuer publishes.
+    pub async fn idm_oidc_upstream_create(
+        &self,
+        name: &str,
+        displayname: &str,
+        issuer: &Url,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<(), ClientError> {
+        let discovery_url = Url::parse(&format!(
+            "{}/.well-known/openid-configuration",
+            issuer.as_str().trim_end_matches('/')
+        ))
+        .map_err(|err| ClientError::InvalidRequest(err.to_string()))?;
+
+        let response = self
+            .client
+            .get(discovery_url)
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ClientError::Http(status, None, body));
+        }
+
+        let discovery: OidcUpstreamDiscovery = response
+            .json()
+            .await
+            .map_err(|err| ClientError::JsonDecode(err, "OidcUpstreamDiscovery".to_string()))?;
+
+        let mut new_upstream = Entry::default();
+        for (attr, value) in [
+            (ATTR_NAME, name.to_string()),
+            (ATTR_DISPLAYNAME, displayname.to_string()),
+            (ATTR_OIDC_UPSTREAM_ISSUER, discovery.issuer.to_string()),
+            (ATTR_OIDC_UPSTREAM_CLIENT_ID, client_id.to_string()),
+            (ATTR_OIDC_UPSTREAM_CLIENT_SECRET, client_secret.to_string()),
+            (
+                ATTR_OIDC_UPSTREAM_AUTHORISATION_ENDPOINT,
+                discovery.authorization_endpoint.to_string(),
+            ),
+            (
+                ATTR_OIDC_UPSTREAM_TOKEN_ENDPOINT,
+                discovery.token_endpoint.to_string(),
+            ),
+            (ATTR_OIDC_UPSTREAM_JWKS_URI, discovery.jwks_uri.to_string()),
+        ] {
+            new_upstream.attrs.insert(attr.to_string(), vec![value]);
+        }
+
+        self.perform_post_request("/v1/oidc_upstream", new_upstream)
+            .await
+    }
+
+    pub async fn idm_oidc_upstream_set_client_secret(
+        &self,
+        id: &str,
+        client_secret: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            format!(
+                "/v1/oidc_upstream/{}/_attr/{}",
+                id, ATTR_OIDC_UPSTREAM_CLIENT_SECRET
+            )
+            .as_str(),
+            vec![client_secret.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_oidc_upstream_delete(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_delete_request(format!("/v1/oidc_upstream/{}", id).as_str())
+            .await
+    }
+}
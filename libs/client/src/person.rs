@@ -1,8 +1,15 @@
 use std::collections::BTreeMap;
+use std::str::FromStr;
 
 use kanidm_proto::constants::*;
-use kanidm_proto::internal::{CredentialStatus, IdentifyUserRequest, IdentifyUserResponse};
-use kanidm_proto::v1::{AccountUnixExtend, Entry, SingleStringRequest, UatStatus};
+use kanidm_proto::internal::{
+    CredentialSoftLockStatus, CredentialStatus, IdentifyUserRequest, IdentifyUserResponse,
+};
+use kanidm_proto::v1::{
+    AccountUnixExtend, Entry, Oauth2SessionStatus, SingleStringRequest, SshPublicKeyExpiry,
+    UatStatus,
+};
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 use crate::{ClientError, KanidmClient};
@@ -39,6 +46,34 @@ impl KanidmClient {
         self.perform_post_request("/v1/person", new_acct).await
     }
 
+    /// Create a contractor, who is a person with a sponsor that is accountable for them. A
+    /// contractor must have an expiry, which is an RFC3339 datetime.
+    pub async fn idm_person_contractor_create(
+        &self,
+        name: &str,
+        displayname: &str,
+        sponsor: &str,
+        expiry: &str,
+    ) -> Result<(), ClientError> {
+        let mut new_acct = Entry {
+            attrs: BTreeMap::new(),
+        };
+        new_acct
+            .attrs
+            .insert(ATTR_NAME.to_string(), vec![name.to_string()]);
+        new_acct
+            .attrs
+            .insert(ATTR_DISPLAYNAME.to_string(), vec![displayname.to_string()]);
+        new_acct
+            .attrs
+            .insert(ATTR_SPONSOR.to_string(), vec![sponsor.to_string()]);
+        new_acct
+            .attrs
+            .insert(ATTR_ACCOUNT_EXPIRE.to_string(), vec![expiry.to_string()]);
+        self.perform_post_request("/v1/person/_contractor", new_acct)
+            .await
+    }
+
     pub async fn idm_person_account_update(
         &self,
         id: &str,
@@ -138,6 +173,22 @@ impl KanidmClient {
         })
     }
 
+    pub async fn idm_person_account_get_credential_softlock(
+        &self,
+        id: &str,
+    ) -> Result<CredentialSoftLockStatus, ClientError> {
+        self.perform_get_request(format!("/v1/person/{}/_credential/_softlock", id).as_str())
+            .await
+    }
+
+    pub async fn idm_person_account_clear_credential_softlock(
+        &self,
+        id: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(format!("/v1/person/{}/_credential/_softlock", id).as_str())
+            .await
+    }
+
     // This helper calls through the credential update session wrappers to
     pub async fn idm_person_account_primary_credential_set_password(
         &self,
@@ -176,6 +227,39 @@ impl KanidmClient {
             .await
     }
 
+    /// Set the time that an ssh public key expires, after which it is no longer distributed
+    /// to clients. If the expiry is `None`, any existing expiry of the key is removed.
+    pub async fn idm_person_account_set_ssh_pubkey_expiry(
+        &self,
+        id: &str,
+        tag: &str,
+        expiry: Option<OffsetDateTime>,
+    ) -> Result<(), ClientError> {
+        let mut values: Vec<String> = self
+            .idm_person_account_get_attr(id, ATTR_SSH_PUBLICKEY_EXPIRY)
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|value| {
+                SshPublicKeyExpiry::from_str(value).map_or(true, |existing| existing.label != tag)
+            })
+            .collect();
+
+        if let Some(expiry) = expiry {
+            let label = tag.to_string();
+            values.push(SshPublicKeyExpiry { label, expiry }.to_string());
+        }
+
+        if values.is_empty() {
+            self.idm_person_account_purge_attr(id, ATTR_SSH_PUBLICKEY_EXPIRY)
+                .await
+        } else {
+            let values: Vec<&str> = values.iter().map(String::as_str).collect();
+            self.idm_person_account_set_attr(id, ATTR_SSH_PUBLICKEY_EXPIRY, &values)
+                .await
+        }
+    }
+
     pub async fn idm_person_account_unix_extend(
         &self,
         id: &str,
@@ -222,6 +306,16 @@ impl KanidmClient {
         .await
     }
 
+    /// Issue a read only session that acts as this person. The expiry is in seconds.
+    pub async fn idm_person_impersonate(
+        &self,
+        id: &str,
+        expiry: Option<u64>,
+    ) -> Result<String, ClientError> {
+        self.perform_post_request(format!("/v1/person/{}/_impersonate", id).as_str(), expiry)
+            .await
+    }
+
     pub async fn idm_account_radius_credential_get(
         &self,
         id: &str,
@@ -243,6 +337,20 @@ impl KanidmClient {
             .await
     }
 
+    /// Sign a PEM encoded certificate signing request, returning a client certificate for
+    /// EAP-TLS.
+    pub async fn idm_account_radius_certificate_issue(
+        &self,
+        id: &str,
+        csr: &str,
+    ) -> Result<String, ClientError> {
+        self.perform_post_request(
+            format!("/v1/person/{}/_radius/_certificate", id).as_str(),
+            csr,
+        )
+        .await
+    }
+
     pub async fn idm_account_list_user_auth_token(
         &self,
         id: &str,
@@ -251,6 +359,14 @@ impl KanidmClient {
             .await
     }
 
+    pub async fn idm_account_list_oauth2_sessions(
+        &self,
+        id: &str,
+    ) -> Result<Vec<Oauth2SessionStatus>, ClientError> {
+        self.perform_get_request(format!("/v1/account/{}/_oauth2_session", id).as_str())
+            .await
+    }
+
     pub async fn idm_account_destroy_user_auth_token(
         &self,
         id: &str,
@@ -0,0 +1,104 @@
+use crate::{ClientError, KanidmClient};
+use kanidm_proto::constants::{
+    ATTR_DISPLAYNAME, ATTR_KEY_ACTION_REVOKE, ATTR_NAME, ATTR_SAML2_ACS_URL, ATTR_SAML2_ENTITY_ID,
+};
+use kanidm_proto::v1::Entry;
+use url::Url;
+
+impl KanidmClient {
+    pub async fn idm_saml2_list(&self) -> Result<Vec<Entry>, ClientError> {
+        self.perform_get_request("/v1/saml2").await
+    }
+
+    pub async fn idm_saml2_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.perform_get_request(format!("/v1/saml2/{}", id).as_str())
+            .await
+    }
+
+    /// Create a service provider. A signing key is generated for it by the server.
+    pub async fn idm_saml2_create(
+        &self,
+        name: &str,
+        displayname: &str,
+        entity_id: &str,
+        acs_url: &Url,
+    ) -> Result<(), ClientError> {
+        let mut new_sp = Entry::default();
+        for (attr, value) in [
+            (ATTR_NAME, name.to_string()),
+            (ATTR_DISPLAYNAME, displayname.to_string()),
+            (ATTR_SAML2_ENTITY_ID, entity_id.to_string()),
+            (ATTR_SAML2_ACS_URL, acs_url.to_string()),
+        ] {
+            new_sp.attrs.insert(attr.to_string(), vec![value]);
+        }
+
+        self.perform_post_request("/v1/saml2", new_sp).await
+    }
+
+    pub async fn idm_saml2_set_acs_urls(
+        &self,
+        id: &str,
+        acs_urls: &[Url],
+    ) -> Result<(), ClientError> {
+        self.perform_put_request(
+            format!("/v1/saml2/{}/_attr/{}", id, ATTR_SAML2_ACS_URL).as_str(),
+            acs_urls.iter().map(Url::to_string).collect::<Vec<_>>(),
+        )
+        .await
+    }
+
+    pub async fn idm_saml2_update_group_map(
+        &self,
+        id: &str,
+        group: &str,
+        values: Vec<&str>,
+    ) -> Result<(), ClientError> {
+        let values: Vec<String> = values.into_iter().map(str::to_string).collect();
+        self.perform_post_request(
+            format!("/v1/saml2/{}/_group_map/{}", id, group).as_str(),
+            values,
+        )
+        .await
+    }
+
+    pub async fn idm_saml2_delete_group_map(
+        &self,
+        id: &str,
+        group: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(format!("/v1/saml2/{}/_group_map/{}", id, group).as_str())
+            .await
+    }
+
+    pub async fn idm_saml2_revoke_key(&self, id: &str, key_id: &str) -> Result<(), ClientError> {
+        self.perform_put_request(
+            format!("/v1/saml2/{}/_attr/{}", id, ATTR_KEY_ACTION_REVOKE).as_str(),
+            vec![key_id.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_saml2_delete(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_delete_request(format!("/v1/saml2/{}", id).as_str())
+            .await
+    }
+
+    /// The metadata of the identity provider, to configure the service provider with.
+    pub async fn idm_saml2_metadata(&self, id: &str) -> Result<String, ClientError> {
+        let response = self
+            .client
+            .get(self.make_url(&format!("/saml2/{}/metadata", id)))
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ClientError::Http(status, None, body));
+        }
+
+        response.text().await.map_err(ClientError::Transport)
+    }
+}
@@ -1,8 +1,14 @@
 use std::collections::BTreeMap;
+use std::str::FromStr;
 
-use kanidm_proto::constants::{ATTR_DISPLAYNAME, ATTR_ENTRY_MANAGED_BY, ATTR_MAIL, ATTR_NAME};
-use kanidm_proto::internal::{ApiToken, CredentialStatus};
-use kanidm_proto::v1::{AccountUnixExtend, ApiTokenGenerate, Entry};
+use kanidm_proto::constants::{
+    ATTR_DISPLAYNAME, ATTR_ENTRY_MANAGED_BY, ATTR_JWT_PUBLIC_KEY, ATTR_MAIL, ATTR_NAME,
+    ATTR_SSH_PUBLICKEY_EXPIRY,
+};
+use kanidm_proto::internal::{ApiToken, ApiTokenPermission, CredentialStatus};
+use kanidm_proto::v1::{
+    AccountUnixExtend, ApiTokenGenerate, Entry, JwtPublicKeyAdd, SshPublicKeyExpiry,
+};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
@@ -161,6 +167,39 @@ impl KanidmClient {
         .await
     }
 
+    /// Set the time that an ssh public key expires, after which it is no longer distributed
+    /// to clients. If the expiry is `None`, any existing expiry of the key is removed.
+    pub async fn idm_service_account_set_ssh_pubkey_expiry(
+        &self,
+        id: &str,
+        tag: &str,
+        expiry: Option<OffsetDateTime>,
+    ) -> Result<(), ClientError> {
+        let mut values: Vec<String> = self
+            .idm_service_account_get_attr(id, ATTR_SSH_PUBLICKEY_EXPIRY)
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|value| {
+                SshPublicKeyExpiry::from_str(value).map_or(true, |existing| existing.label != tag)
+            })
+            .collect();
+
+        if let Some(expiry) = expiry {
+            let label = tag.to_string();
+            values.push(SshPublicKeyExpiry { label, expiry }.to_string());
+        }
+
+        if values.is_empty() {
+            self.idm_service_account_purge_attr(id, ATTR_SSH_PUBLICKEY_EXPIRY)
+                .await
+        } else {
+            let values: Vec<&str> = values.iter().map(String::as_str).collect();
+            self.idm_service_account_set_attr(id, ATTR_SSH_PUBLICKEY_EXPIRY, &values)
+                .await
+        }
+    }
+
     pub async fn idm_service_account_unix_extend(
         &self,
         // The username or uuid of the account
@@ -241,6 +280,28 @@ impl KanidmClient {
             label: label.to_string(),
             expiry,
             read_write,
+            permissions: Vec::new(),
+        };
+        self.perform_post_request(
+            format!("/v1/service_account/{}/_api_token", id).as_str(),
+            new_token,
+        )
+        .await
+    }
+
+    /// Generate a read write api token that may only modify what the permissions allow.
+    pub async fn idm_service_account_generate_scoped_api_token(
+        &self,
+        id: &str,
+        label: &str,
+        expiry: Option<OffsetDateTime>,
+        permissions: Vec<ApiTokenPermission>,
+    ) -> Result<String, ClientError> {
+        let new_token = ApiTokenGenerate {
+            label: label.to_string(),
+            expiry,
+            read_write: true,
+            permissions,
         };
         self.perform_post_request(
             format!("/v1/service_account/{}/_api_token", id).as_str(),
@@ -264,4 +325,42 @@ impl KanidmClient {
         )
         .await
     }
+
+    /// List the labels of the public keys that may sign jwt assertions for this account.
+    pub async fn idm_service_account_list_jwt_public_key(
+        &self,
+        id: &str,
+    ) -> Result<Vec<String>, ClientError> {
+        self.idm_service_account_get_attr(id, ATTR_JWT_PUBLIC_KEY)
+            .await
+            .map(|keys| keys.unwrap_or_default())
+    }
+
+    pub async fn idm_service_account_add_jwt_public_key(
+        &self,
+        id: &str,
+        label: &str,
+        public_key_pem: &str,
+    ) -> Result<(), ClientError> {
+        let req = JwtPublicKeyAdd {
+            label: label.to_string(),
+            public_key: public_key_pem.to_string(),
+        };
+        self.perform_post_request(
+            format!("/v1/service_account/{}/_jwt_public_key", id).as_str(),
+            req,
+        )
+        .await
+    }
+
+    pub async fn idm_service_account_delete_jwt_public_key(
+        &self,
+        id: &str,
+        label: &str,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request(
+            format!("/v1/service_account/{}/_jwt_public_key/{}", id, label).as_str(),
+        )
+        .await
+    }
 }
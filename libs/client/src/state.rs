@@ -0,0 +1,498 @@
+//! Compare a declarative state with the server, and apply the modifications that are needed
+//! for the server to match it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use kanidm_proto::constants::{
+    ATTR_ALLOW_PRIMARY_CRED_FALLBACK, ATTR_AUTH_PASSWORD_HISTORY_COUNT,
+    ATTR_AUTH_PASSWORD_MINIMUM_LENGTH, ATTR_AUTH_SESSION_EXPIRY, ATTR_CLASS,
+    ATTR_CREDENTIAL_TYPE_MINIMUM, ATTR_DESCRIPTION, ATTR_DISPLAYNAME, ATTR_ENTRY_MANAGED_BY,
+    ATTR_MAIL, ATTR_MEMBER, ATTR_NAME, ATTR_OAUTH2_RS_ORIGIN, ATTR_OAUTH2_RS_ORIGIN_LANDING,
+    ATTR_OAUTH2_RS_SCOPE_MAP, ATTR_OAUTH2_RS_SUP_SCOPE_MAP, ATTR_PRIVILEGE_EXPIRY,
+    ENTRYCLASS_ACCOUNT_POLICY,
+};
+use kanidm_proto::v1::{
+    AccountPolicyState, DeclarativeState, Entry, GroupState, Oauth2ClientState, StateChange,
+    StateEntryKind,
+};
+
+use crate::{ClientError, KanidmClient};
+
+type Attrs = BTreeMap<String, Vec<String>>;
+
+impl KanidmClient {
+    /// Determine the modifications that are needed for the server to match the declarative
+    /// state, in the order that they must be applied.
+    pub async fn state_plan(
+        &self,
+        state: &DeclarativeState,
+    ) -> Result<Vec<StateChange>, ClientError> {
+        let mut changes = Vec::new();
+
+        for group in state.groups.iter() {
+            let current = self.idm_group_get(&group.name).await?;
+            plan_group(&mut changes, group, current);
+        }
+
+        for client in state.oauth2_clients.iter() {
+            let current = self.idm_oauth2_rs_get(&client.name).await?;
+            plan_oauth2_client(&mut changes, client, current);
+        }
+
+        Ok(changes)
+    }
+
+    pub async fn state_apply_change(&self, change: &StateChange) -> Result<(), ClientError> {
+        match change {
+            StateChange::CreateGroup { name } => self.idm_group_create(name, None).await,
+            StateChange::CreateOauth2Client {
+                name,
+                displayname,
+                origin_landing,
+                public,
+            } => {
+                if *public {
+                    self.idm_oauth2_rs_public_create(name, displayname, origin_landing.as_str())
+                        .await
+                } else {
+                    self.idm_oauth2_rs_basic_create(name, displayname, origin_landing.as_str())
+                        .await
+                }
+            }
+            StateChange::EnableAccountPolicy { group } => {
+                self.group_account_policy_enable(group).await
+            }
+            StateChange::SetAttr {
+                kind: StateEntryKind::Group,
+                name,
+                attr,
+                values,
+            } => {
+                self.perform_put_request(&format!("/v1/group/{}/_attr/{}", name, attr), values)
+                    .await
+            }
+            StateChange::PurgeAttr {
+                kind: StateEntryKind::Group,
+                name,
+                attr,
+            } => self.idm_group_purge_attr(name, attr).await,
+            StateChange::SetAttr {
+                kind: StateEntryKind::Oauth2Client,
+                name,
+                attr,
+                values,
+            } => {
+                let update = Entry {
+                    attrs: BTreeMap::from([(attr.clone(), values.clone())]),
+                };
+                self.perform_patch_request(&format!("/v1/oauth2/{}", name), update)
+                    .await
+            }
+            StateChange::PurgeAttr {
+                kind: StateEntryKind::Oauth2Client,
+                name,
+                attr,
+            } => {
+                let update = Entry {
+                    attrs: BTreeMap::from([(attr.clone(), Vec::new())]),
+                };
+                self.perform_patch_request(&format!("/v1/oauth2/{}", name), update)
+                    .await
+            }
+            StateChange::SetScopeMap {
+                client,
+                group,
+                scopes,
+                supplementary,
+            } => {
+                let scopes = scopes.iter().map(String::as_str).collect();
+                if *supplementary {
+                    self.idm_oauth2_rs_update_sup_scope_map(client, group, scopes)
+                        .await
+                } else {
+                    self.idm_oauth2_rs_update_scope_map(client, group, scopes)
+                        .await
+                }
+            }
+            StateChange::DeleteScopeMap {
+                client,
+                group,
+                supplementary,
+            } => {
+                if *supplementary {
+                    self.idm_oauth2_rs_delete_sup_scope_map(client, group).await
+                } else {
+                    self.idm_oauth2_rs_delete_scope_map(client, group).await
+                }
+            }
+        }
+    }
+}
+
+fn plan_group(changes: &mut Vec<StateChange>, group: &GroupState, current: Option<Entry>) {
+    let current = match current {
+        Some(entry) => entry.attrs,
+        None => {
+            changes.push(StateChange::CreateGroup {
+                name: group.name.clone(),
+            });
+            Attrs::new()
+        }
+    };
+
+    let mut plan = |attr: &str, desired: Option<Vec<String>>| {
+        plan_attr(
+            changes,
+            StateEntryKind::Group,
+            &group.name,
+            &current,
+            attr,
+            desired,
+        )
+    };
+
+    plan(ATTR_DESCRIPTION, group.description.clone().map(|d| vec![d]));
+    plan(ATTR_MEMBER, group.members.clone());
+    plan(
+        ATTR_ENTRY_MANAGED_BY,
+        group.entry_managed_by.clone().map(|e| vec![e]),
+    );
+    plan(ATTR_MAIL, group.mail.clone());
+
+    let Some(account_policy) = &group.account_policy else {
+        return;
+    };
+
+    let AccountPolicyState {
+        authsession_expiry,
+        privilege_expiry,
+        credential_type_minimum,
+        password_minimum_length,
+        password_history_count,
+        allow_primary_cred_fallback,
+    } = account_policy;
+
+    let account_policy_attrs = [
+        (
+            ATTR_AUTH_SESSION_EXPIRY,
+            authsession_expiry.map(|v| v.to_string()),
+        ),
+        (
+            ATTR_PRIVILEGE_EXPIRY,
+            privilege_expiry.map(|v| v.to_string()),
+        ),
+        (
+            ATTR_CREDENTIAL_TYPE_MINIMUM,
+            credential_type_minimum.clone(),
+        ),
+        (
+            ATTR_AUTH_PASSWORD_MINIMUM_LENGTH,
+            password_minimum_length.map(|v| v.to_string()),
+        ),
+        (
+            ATTR_AUTH_PASSWORD_HISTORY_COUNT,
+            password_history_count.map(|v| v.to_string()),
+        ),
+        (
+            ATTR_ALLOW_PRIMARY_CRED_FALLBACK,
+            allow_primary_cred_fallback.map(|v| v.to_string()),
+        ),
+    ];
+
+    let has_account_policy = current
+        .get(ATTR_CLASS)
+        .is_some_and(|classes| classes.iter().any(|c| c == ENTRYCLASS_ACCOUNT_POLICY));
+
+    // The account policy attributes are only valid once account policy is enabled.
+    if !has_account_policy {
+        changes.push(StateChange::EnableAccountPolicy {
+            group: group.name.clone(),
+        });
+    }
+
+    for (attr, desired) in account_policy_attrs {
+        plan_attr(
+            changes,
+            StateEntryKind::Group,
+            &group.name,
+            &current,
+            attr,
+            desired.map(|v| vec![v]),
+        );
+    }
+}
+
+fn plan_oauth2_client(
+    changes: &mut Vec<StateChange>,
+    client: &Oauth2ClientState,
+    current: Option<Entry>,
+) {
+    let current = match current {
+        Some(entry) => entry.attrs,
+        None => {
+            changes.push(StateChange::CreateOauth2Client {
+                name: client.name.clone(),
+                displayname: client.displayname.clone(),
+                origin_landing: client.origin_landing.clone(),
+                public: client.public,
+            });
+            // These are set when the client is created.
+            Attrs::from([
+                (ATTR_NAME.to_string(), vec![client.name.clone()]),
+                (
+                    ATTR_DISPLAYNAME.to_string(),
+                    vec![client.displayname.clone()],
+                ),
+                (
+                    ATTR_OAUTH2_RS_ORIGIN_LANDING.to_string(),
+                    vec![client.origin_landing.to_string()],
+                ),
+            ])
+        }
+    };
+
+    let kind = StateEntryKind::Oauth2Client;
+
+    plan_attr(
+        changes,
+        kind,
+        &client.name,
+        &current,
+        ATTR_DISPLAYNAME,
+        Some(vec![client.displayname.clone()]),
+    );
+    plan_attr(
+        changes,
+        kind,
+        &client.name,
+        &current,
+        ATTR_OAUTH2_RS_ORIGIN_LANDING,
+        Some(vec![client.origin_landing.to_string()]),
+    );
+    plan_attr(
+        changes,
+        kind,
+        &client.name,
+        &current,
+        ATTR_OAUTH2_RS_ORIGIN,
+        client
+            .redirect_urls
+            .as_ref()
+            .map(|urls| urls.iter().map(|u| u.to_string()).collect()),
+    );
+
+    for (attr, desired, supplementary) in [
+        (ATTR_OAUTH2_RS_SCOPE_MAP, &client.scope_maps, false),
+        (ATTR_OAUTH2_RS_SUP_SCOPE_MAP, &client.sup_scope_maps, true),
+    ] {
+        let Some(desired) = desired else {
+            continue;
+        };
+
+        let current_maps = parse_scope_maps(current.get(attr));
+
+        for (group, scopes) in desired.iter() {
+            let unchanged = current_maps
+                .iter()
+                .any(|(c_group, c_scopes)| same_reference(group, c_group) && c_scopes == scopes);
+
+            if !unchanged {
+                changes.push(StateChange::SetScopeMap {
+                    client: client.name.clone(),
+                    group: group.clone(),
+                    scopes: scopes.clone(),
+                    supplementary,
+                });
+            }
+        }
+
+        for c_group in current_maps.keys() {
+            if !desired.keys().any(|group| same_reference(group, c_group)) {
+                changes.push(StateChange::DeleteScopeMap {
+                    client: client.name.clone(),
+                    group: c_group.clone(),
+                    supplementary,
+                });
+            }
+        }
+    }
+}
+
+fn plan_attr(
+    changes: &mut Vec<StateChange>,
+    kind: StateEntryKind,
+    name: &str,
+    current: &Attrs,
+    attr: &str,
+    desired: Option<Vec<String>>,
+) {
+    // Attributes that aren't described are left unchanged.
+    let Some(desired) = desired else {
+        return;
+    };
+
+    let current = current.get(attr).map(Vec::as_slice).unwrap_or_default();
+
+    let unchanged = current.len() == desired.len()
+        && desired
+            .iter()
+            .all(|d| current.iter().any(|c| same_reference(d, c)));
+
+    if unchanged {
+        return;
+    }
+
+    if desired.is_empty() {
+        changes.push(StateChange::PurgeAttr {
+            kind,
+            name: name.to_string(),
+            attr: attr.to_string(),
+        });
+    } else {
+        changes.push(StateChange::SetAttr {
+            kind,
+            name: name.to_string(),
+            attr: attr.to_string(),
+            values: desired,
+        });
+    }
+}
+
+/// References are returned by the server as spns, but may be described by their name.
+fn same_reference(desired: &str, current: &str) -> bool {
+    desired == current
+        || current
+            .split_once('@')
+            .is_some_and(|(name, _)| name == desired)
+}
+
+/// Scope maps are returned by the server in the form `group@domain: {"openid", "email"}`.
+fn parse_scope_maps(values: Option<&Vec<String>>) -> BTreeMap<String, BTreeSet<String>> {
+    values
+        .into_iter()
+        .flatten()
+        .filter_map(|value| {
+            let (group, scopes) = value.split_once(": ")?;
+            let scopes = scopes
+                .trim_start_matches('{')
+                .trim_end_matches('}')
+                .split(", ")
+                .map(|scope| scope.trim_matches('"').to_string())
+                .filter(|scope| !scope.is_empty())
+                .collect();
+            Some((group.to_string(), scopes))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_group() {
+        let group = GroupState {
+            name: "eng".to_string(),
+            description: None,
+            members: Some(vec!["alice".to_string(), "bob@example.com".to_string()]),
+            entry_managed_by: None,
+            mail: Some(Vec::new()),
+            account_policy: Some(AccountPolicyState {
+                privilege_expiry: Some(900),
+                ..Default::default()
+            }),
+        };
+
+        // A group that doesn't exist is created with everything that is described.
+        let mut changes = Vec::new();
+        plan_group(&mut changes, &group, None);
+        assert_eq!(
+            changes,
+            vec![
+                StateChange::CreateGroup {
+                    name: "eng".to_string()
+                },
+                StateChange::SetAttr {
+                    kind: StateEntryKind::Group,
+                    name: "eng".to_string(),
+                    attr: ATTR_MEMBER.to_string(),
+                    values: vec!["alice".to_string(), "bob@example.com".to_string()],
+                },
+                StateChange::EnableAccountPolicy {
+                    group: "eng".to_string()
+                },
+                StateChange::SetAttr {
+                    kind: StateEntryKind::Group,
+                    name: "eng".to_string(),
+                    attr: ATTR_PRIVILEGE_EXPIRY.to_string(),
+                    values: vec!["900".to_string()],
+                },
+            ]
+        );
+
+        // A group that already matches needs no changes, even though the server returns spns.
+        let current = Entry {
+            attrs: Attrs::from([
+                (
+                    ATTR_CLASS.to_string(),
+                    vec!["group".to_string(), ENTRYCLASS_ACCOUNT_POLICY.to_string()],
+                ),
+                (
+                    ATTR_MEMBER.to_string(),
+                    vec![
+                        "bob@example.com".to_string(),
+                        "alice@example.com".to_string(),
+                    ],
+                ),
+                (ATTR_PRIVILEGE_EXPIRY.to_string(), vec!["900".to_string()]),
+            ]),
+        };
+        let mut changes = Vec::new();
+        plan_group(&mut changes, &group, Some(current));
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_plan_oauth2_client_scope_maps() {
+        let client = Oauth2ClientState {
+            name: "wiki".to_string(),
+            displayname: "Wiki".to_string(),
+            origin_landing: "https://wiki.example.com/".parse().expect("Invalid url"),
+            public: false,
+            redirect_urls: None,
+            scope_maps: Some(BTreeMap::from([(
+                "eng".to_string(),
+                BTreeSet::from(["openid".to_string(), "email".to_string()]),
+            )])),
+            sup_scope_maps: None,
+        };
+
+        let current = Entry {
+            attrs: Attrs::from([
+                (ATTR_DISPLAYNAME.to_string(), vec!["Wiki".to_string()]),
+                (
+                    ATTR_OAUTH2_RS_ORIGIN_LANDING.to_string(),
+                    vec!["https://wiki.example.com/".to_string()],
+                ),
+                (
+                    ATTR_OAUTH2_RS_SCOPE_MAP.to_string(),
+                    vec![
+                        r#"eng@example.com: {"email", "openid"}"#.to_string(),
+                        r#"sales@example.com: {"openid"}"#.to_string(),
+                    ],
+                ),
+            ]),
+        };
+
+        let mut changes = Vec::new();
+        plan_oauth2_client(&mut changes, &client, Some(current));
+        assert_eq!(
+            changes,
+            vec![StateChange::DeleteScopeMap {
+                client: "wiki".to_string(),
+                group: "sales@example.com".to_string(),
+                supplementary: false,
+            }]
+        );
+    }
+}
@@ -56,6 +56,24 @@ impl KanidmClient {
         .await
     }
 
+    pub async fn idm_sync_account_get_conflicts(
+        &self,
+        id: &str,
+    ) -> Result<Vec<String>, ClientError> {
+        self.perform_get_request(format!("/v1/sync_account/{}/_attr/sync_conflict", id).as_str())
+            .await
+            .map(|values: Option<Vec<String>>| values.unwrap_or_default())
+    }
+
+    pub async fn idm_sync_account_clear_conflicts(&self, id: &str) -> Result<(), ClientError> {
+        let m: Vec<String> = Vec::with_capacity(0);
+        self.perform_put_request(
+            format!("/v1/sync_account/{}/_attr/sync_conflict", id).as_str(),
+            m,
+        )
+        .await
+    }
+
     pub async fn idm_sync_account_create(
         &self,
         name: &str,
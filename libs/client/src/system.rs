@@ -1,4 +1,6 @@
 use crate::{ClientError, KanidmClient};
+use kanidm_proto::internal::{AccessControlPreview, AccessControlPreviewQuery};
+use kanidm_proto::v1::Entry;
 
 impl KanidmClient {
     pub async fn system_password_badlist_get(&self) -> Result<Vec<String>, ClientError> {
@@ -40,4 +42,45 @@ impl KanidmClient {
         self.perform_delete_request_with_body("/v1/system/_attr/denied_name", list)
             .await
     }
+
+    pub async fn system_denied_password_terms_get(&self) -> Result<Vec<String>, ClientError> {
+        let list: Option<Vec<String>> = self
+            .perform_get_request("/v1/system/_attr/denied_password_term")
+            .await?;
+        Ok(list.unwrap_or_default())
+    }
+
+    pub async fn system_denied_password_terms_append(
+        &self,
+        list: &Vec<String>,
+    ) -> Result<(), ClientError> {
+        self.perform_post_request("/v1/system/_attr/denied_password_term", list)
+            .await
+    }
+
+    pub async fn system_denied_password_terms_remove(
+        &self,
+        list: &Vec<String>,
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body("/v1/system/_attr/denied_password_term", list)
+            .await
+    }
+
+    /// List the records of the migrations that have been applied to the database.
+    pub async fn system_migrations_list(&self) -> Result<Vec<Entry>, ClientError> {
+        self.perform_get_request("/v1/system/_migrations").await
+    }
+
+    /// Preview how the staged access controls would change the access of a sample of
+    /// accounts, compared to the enforced access controls.
+    pub async fn system_access_control_preview(
+        &self,
+        sample: Option<usize>,
+    ) -> Result<AccessControlPreview, ClientError> {
+        self.perform_get_request_query(
+            "/v1/system/_access_control_preview",
+            Some(AccessControlPreviewQuery { sample }),
+        )
+        .await
+    }
 }
@@ -0,0 +1,103 @@
+use crate::{ClientError, KanidmClient};
+use kanidm_proto::constants::{
+    ATTR_DESCRIPTION, ATTR_NAME, ATTR_WEBHOOK_FILTER, ATTR_WEBHOOK_SECRET, ATTR_WEBHOOK_URL,
+};
+use kanidm_proto::internal::WebhookDelivery;
+use kanidm_proto::v1::Entry;
+use url::Url;
+
+impl KanidmClient {
+    pub async fn idm_webhook_list(&self) -> Result<Vec<Entry>, ClientError> {
+        self.perform_get_request("/v1/webhook").await
+    }
+
+    pub async fn idm_webhook_get(&self, id: &str) -> Result<Option<Entry>, ClientError> {
+        self.perform_get_request(format!("/v1/webhook/{}", id).as_str())
+            .await
+    }
+
+    pub async fn idm_webhook_create(
+        &self,
+        name: &str,
+        url: &Url,
+        description: Option<&str>,
+        filters: &[String],
+    ) -> Result<(), ClientError> {
+        let mut new_webhook = Entry::default();
+        new_webhook
+            .attrs
+            .insert(ATTR_NAME.to_string(), vec![name.to_string()]);
+        new_webhook
+            .attrs
+            .insert(ATTR_WEBHOOK_URL.to_string(), vec![url.to_string()]);
+        if let Some(description) = description {
+            new_webhook
+                .attrs
+                .insert(ATTR_DESCRIPTION.to_string(), vec![description.to_string()]);
+        }
+        if !filters.is_empty() {
+            new_webhook
+                .attrs
+                .insert(ATTR_WEBHOOK_FILTER.to_string(), filters.to_vec());
+        }
+        self.perform_post_request("/v1/webhook", new_webhook).await
+    }
+
+    pub async fn idm_webhook_delete(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_delete_request(format!("/v1/webhook/{}", id).as_str())
+            .await
+    }
+
+    pub async fn idm_webhook_set_url(&self, id: &str, url: &Url) -> Result<(), ClientError> {
+        self.perform_put_request(
+            format!("/v1/webhook/{}/_attr/{}", id, ATTR_WEBHOOK_URL).as_str(),
+            vec![url.to_string()],
+        )
+        .await
+    }
+
+    pub async fn idm_webhook_add_filters(
+        &self,
+        id: &str,
+        filters: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_post_request(
+            format!("/v1/webhook/{}/_attr/{}", id, ATTR_WEBHOOK_FILTER).as_str(),
+            filters,
+        )
+        .await
+    }
+
+    pub async fn idm_webhook_remove_filters(
+        &self,
+        id: &str,
+        filters: &[String],
+    ) -> Result<(), ClientError> {
+        self.perform_delete_request_with_body(
+            format!("/v1/webhook/{}/_attr/{}", id, ATTR_WEBHOOK_FILTER).as_str(),
+            filters,
+        )
+        .await
+    }
+
+    pub async fn idm_webhook_get_secret(&self, id: &str) -> Result<Option<String>, ClientError> {
+        self.perform_get_request(format!("/v1/webhook/{}/_secret", id).as_str())
+            .await
+    }
+
+    /// Remove the secret of the webhook, causing the server to generate a new one.
+    pub async fn idm_webhook_rotate_secret(&self, id: &str) -> Result<(), ClientError> {
+        self.perform_delete_request(
+            format!("/v1/webhook/{}/_attr/{}", id, ATTR_WEBHOOK_SECRET).as_str(),
+        )
+        .await
+    }
+
+    pub async fn idm_webhook_list_deliveries(
+        &self,
+        id: &str,
+    ) -> Result<Vec<WebhookDelivery>, ClientError> {
+        self.perform_get_request(format!("/v1/webhook/{}/_deliveries", id).as_str())
+            .await
+    }
+}
@@ -0,0 +1,741 @@
+//! The parts of Kerberos 5 (RFC 4120) that are needed to issue service tickets without a KDC.
+//! Only the aes256-cts-hmac-sha1-96 encryption type of RFC 3962 is supported. Tickets are
+//! encoded directly as DER, and are delivered to clients in a credential cache, while the
+//! keys of services are delivered in a keytab. Both file formats are those of MIT Kerberos.
+
+use crate::CryptoError;
+
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use openssl::symm::{Cipher, Crypter, Mode};
+use rand::Rng;
+use std::fmt;
+
+pub const KRB5_ENCTYPE_AES256_CTS_HMAC_SHA1_96: i32 = 18;
+pub const KRB5_NT_PRINCIPAL: i32 = 1;
+pub const KRB5_NT_SRV_HST: i32 = 3;
+
+/// The length of the keys of aes256-cts-hmac-sha1-96.
+pub const KRB5_AES256_KEY_LEN: usize = 32;
+
+/// The key usage of the encrypted part of a ticket (RFC 4120, section 7.5.1).
+const KEY_USAGE_TICKET: u32 = 2;
+
+const AES_BLOCK_LEN: usize = 16;
+const HMAC_SHA1_96_LEN: usize = 12;
+
+const KERBEROS_SERVICE_KEY_CONTEXT: &[u8] = b"kanidm kerberos service key";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KerberosPrincipal {
+    pub name_type: i32,
+    pub components: Vec<String>,
+    pub realm: String,
+}
+
+impl KerberosPrincipal {
+    pub fn user(name: &str, realm: &str) -> Self {
+        KerberosPrincipal {
+            name_type: KRB5_NT_PRINCIPAL,
+            components: vec![name.to_string()],
+            realm: realm.to_string(),
+        }
+    }
+
+    /// Parse a service principal name of the form `service/hostname`.
+    pub fn service(service_principal: &str, realm: &str) -> Option<Self> {
+        let (service, hostname) = service_principal.split_once('/')?;
+
+        let is_valid = |component: &str| {
+            !component.is_empty()
+                && component
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_')
+        };
+
+        if !is_valid(service) || !is_valid(hostname) {
+            return None;
+        }
+
+        Some(KerberosPrincipal {
+            name_type: KRB5_NT_SRV_HST,
+            components: vec![service.to_string(), hostname.to_string()],
+            realm: realm.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for KerberosPrincipal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.components.join("/"), self.realm)
+    }
+}
+
+/// A service ticket together with the session key that the client uses with it.
+#[derive(Debug, Clone)]
+pub struct KerberosCredential {
+    pub client: KerberosPrincipal,
+    pub service: KerberosPrincipal,
+    pub session_key: Vec<u8>,
+    pub auth_time: u64,
+    pub end_time: u64,
+    pub flags: u32,
+    /// The DER encoded ticket.
+    pub ticket: Vec<u8>,
+}
+
+/// A long term key of a service, and the version number that tickets refer to it by.
+#[derive(Debug, Clone)]
+pub struct KerberosServiceKey {
+    pub kvno: u32,
+    pub key: Vec<u8>,
+}
+
+/// Generate a new random aes256 key.
+pub fn kerberos_generate_key() -> Vec<u8> {
+    let mut key = vec![0; KRB5_AES256_KEY_LEN];
+    rand::thread_rng().fill(key.as_mut_slice());
+    key
+}
+
+/// Derive the key of a service principal from a secret of the key subsystem, so that the keys
+/// of every service are replaced when the secret is rotated.
+pub fn kerberos_derive_service_key(
+    secret: &[u8],
+    service: &KerberosPrincipal,
+) -> Result<Vec<u8>, CryptoError> {
+    let pkey = PKey::hmac(secret)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
+    signer.update(KERBEROS_SERVICE_KEY_CONTEXT)?;
+    signer.update(&[0])?;
+    signer.update(service.to_string().as_bytes())?;
+    signer.sign_to_vec().map_err(CryptoError::from)
+}
+
+/// Issue a ticket for a service on behalf of a client, encrypted with the key of the service.
+pub fn kerberos_issue_ticket(
+    client: &KerberosPrincipal,
+    service: &KerberosPrincipal,
+    service_key: &KerberosServiceKey,
+    auth_time: u64,
+    end_time: u64,
+) -> Result<KerberosCredential, CryptoError> {
+    let session_key = kerberos_generate_key();
+    // No flags are set, as the ticket can't be forwarded or renewed.
+    let flags = 0;
+
+    // EncTicketPart (RFC 4120, section 5.3)
+    let enc_ticket_part = der_application(
+        3,
+        &der_sequence(&[
+            der_context(0, &der_flags(flags)),
+            der_context(1, &der_encryption_key(&session_key)),
+            der_context(2, &der_general_string(&client.realm)),
+            der_context(3, &der_principal_name(client)),
+            der_context(
+                4,
+                // TransitedEncoding, with no realms transited.
+                &der_sequence(&[
+                    der_context(0, &der_integer(1)),
+                    der_context(1, &der_octet_string(&[])),
+                ]),
+            ),
+            der_context(5, &der_time(auth_time)),
+            der_context(6, &der_time(auth_time)),
+            der_context(7, &der_time(end_time)),
+        ]),
+    );
+
+    let cipher = kerberos_encrypt(&service_key.key, KEY_USAGE_TICKET, &enc_ticket_part)?;
+
+    // Ticket (RFC 4120, section 5.3)
+    let ticket = der_application(
+        1,
+        &der_sequence(&[
+            der_context(0, &der_integer(5)),
+            der_context(1, &der_general_string(&service.realm)),
+            der_context(2, &der_principal_name(service)),
+            der_context(
+                3,
+                &der_sequence(&[
+                    der_context(0, &der_integer(KRB5_ENCTYPE_AES256_CTS_HMAC_SHA1_96 as i64)),
+                    der_context(1, &der_integer(service_key.kvno as i64)),
+                    der_context(2, &der_octet_string(&cipher)),
+                ]),
+            ),
+        ]),
+    );
+
+    Ok(KerberosCredential {
+        client: client.clone(),
+        service: service.clone(),
+        session_key,
+        auth_time,
+        end_time,
+        flags,
+        ticket,
+    })
+}
+
+/// Encode the keys of services as an MIT keytab (version 0x0502).
+pub fn kerberos_keytab(entries: &[(KerberosPrincipal, KerberosServiceKey)]) -> Vec<u8> {
+    let mut keytab = vec![0x05, 0x02];
+
+    for (principal, service_key) in entries {
+        let mut entry = Vec::new();
+        entry.extend_from_slice(&(principal.components.len() as u16).to_be_bytes());
+        keytab_counted(&mut entry, principal.realm.as_bytes());
+        for component in principal.components.iter() {
+            keytab_counted(&mut entry, component.as_bytes());
+        }
+        entry.extend_from_slice(&(principal.name_type as u32).to_be_bytes());
+        // The timestamp is the time the key was written, which is not meaningful here.
+        entry.extend_from_slice(&0u32.to_be_bytes());
+        entry.push(service_key.kvno as u8);
+        entry.extend_from_slice(&(KRB5_ENCTYPE_AES256_CTS_HMAC_SHA1_96 as u16).to_be_bytes());
+        keytab_counted(&mut entry, &service_key.key);
+        // The full key version, as the 8 bit field above is truncated.
+        entry.extend_from_slice(&service_key.kvno.to_be_bytes());
+
+        keytab.extend_from_slice(&(entry.len() as i32).to_be_bytes());
+        keytab.extend_from_slice(&entry);
+    }
+
+    keytab
+}
+
+fn keytab_counted(buf: &mut Vec<u8>, value: &[u8]) {
+    buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buf.extend_from_slice(value);
+}
+
+/// Encode credentials as an MIT credential cache (version 0x0504). The client of the first
+/// credential is the default principal of the cache.
+pub fn kerberos_ccache(credentials: &[KerberosCredential]) -> Vec<u8> {
+    let mut ccache = vec![0x05, 0x04];
+    // No header tags.
+    ccache.extend_from_slice(&0u16.to_be_bytes());
+
+    if let Some(credential) = credentials.first() {
+        ccache_principal(&mut ccache, &credential.client);
+    }
+
+    for credential in credentials {
+        ccache_principal(&mut ccache, &credential.client);
+        ccache_principal(&mut ccache, &credential.service);
+        ccache.extend_from_slice(&(KRB5_ENCTYPE_AES256_CTS_HMAC_SHA1_96 as u16).to_be_bytes());
+        ccache_counted(&mut ccache, &credential.session_key);
+        // authtime, starttime, endtime and renew_till
+        for time in [
+            credential.auth_time,
+            credential.auth_time,
+            credential.end_time,
+            0,
+        ] {
+            ccache.extend_from_slice(&(time as u32).to_be_bytes());
+        }
+        // is_skey
+        ccache.push(0);
+        ccache.extend_from_slice(&credential.flags.to_be_bytes());
+        // No addresses or authorization data.
+        ccache.extend_from_slice(&0u32.to_be_bytes());
+        ccache.extend_from_slice(&0u32.to_be_bytes());
+        ccache_counted(&mut ccache, &credential.ticket);
+        // No second ticket.
+        ccache_counted(&mut ccache, &[]);
+    }
+
+    ccache
+}
+
+fn ccache_principal(buf: &mut Vec<u8>, principal: &KerberosPrincipal) {
+    buf.extend_from_slice(&(principal.name_type as u32).to_be_bytes());
+    buf.extend_from_slice(&(principal.components.len() as u32).to_be_bytes());
+    ccache_counted(buf, principal.realm.as_bytes());
+    for component in principal.components.iter() {
+        ccache_counted(buf, component.as_bytes());
+    }
+}
+
+fn ccache_counted(buf: &mut Vec<u8>, value: &[u8]) {
+    buf.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    buf.extend_from_slice(value);
+}
+
+/// Encrypt with aes256-cts-hmac-sha1-96 (RFC 3961, section 5.3 and RFC 3962).
+pub fn kerberos_encrypt(key: &[u8], usage: u32, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let ke = derive_key(key, usage, 0xAA)?;
+    let ki = derive_key(key, usage, 0x55)?;
+
+    let mut confounded = vec![0; AES_BLOCK_LEN];
+    rand::thread_rng().fill(confounded.as_mut_slice());
+    confounded.extend_from_slice(plaintext);
+
+    let mut ciphertext = aes_cts_encrypt(&ke, &confounded)?;
+    let mac = hmac_sha1_96(&ki, &confounded)?;
+    ciphertext.extend_from_slice(&mac);
+
+    Ok(ciphertext)
+}
+
+/// Decrypt and verify data that was encrypted with [kerberos_encrypt].
+pub fn kerberos_decrypt(key: &[u8], usage: u32, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if ciphertext.len() < AES_BLOCK_LEN + HMAC_SHA1_96_LEN {
+        return Err(CryptoError::KerberosCiphertextInvalid);
+    }
+
+    let ke = derive_key(key, usage, 0xAA)?;
+    let ki = derive_key(key, usage, 0x55)?;
+
+    let (ciphertext, mac) = ciphertext.split_at(ciphertext.len() - HMAC_SHA1_96_LEN);
+    let confounded = aes_cts_decrypt(&ke, ciphertext)?;
+
+    let expected_mac = hmac_sha1_96(&ki, &confounded)?;
+    if !memcmp::eq(&expected_mac, mac) {
+        return Err(CryptoError::KerberosCiphertextInvalid);
+    }
+
+    Ok(confounded[AES_BLOCK_LEN..].to_vec())
+}
+
+fn hmac_sha1_96(key: &[u8], data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let pkey = PKey::hmac(key)?;
+    let mut signer = Signer::new(MessageDigest::sha1(), &pkey)?;
+    signer.update(data)?;
+    let mut mac = signer.sign_to_vec()?;
+    mac.truncate(HMAC_SHA1_96_LEN);
+    Ok(mac)
+}
+
+/// DK(key, usage | kind) from RFC 3961, section 5.1. For aes the derived key is the output
+/// of DR, as random-to-key is the identity function.
+fn derive_key(key: &[u8], usage: u32, kind: u8) -> Result<Vec<u8>, CryptoError> {
+    let mut constant = usage.to_be_bytes().to_vec();
+    constant.push(kind);
+
+    let mut block = nfold(&constant, AES_BLOCK_LEN);
+    let mut derived = Vec::with_capacity(key.len());
+    while derived.len() < key.len() {
+        block = aes_block(key, &block, Mode::Encrypt)?;
+        derived.extend_from_slice(&block);
+    }
+    derived.truncate(key.len());
+
+    Ok(derived)
+}
+
+/// The n-fold operation of RFC 3961, section 5.1, as implemented by MIT Kerberos.
+fn nfold(input: &[u8], out_len: usize) -> Vec<u8> {
+    let in_len = input.len();
+    let in_bits = in_len << 3;
+
+    let gcd = {
+        let (mut a, mut b) = (out_len, in_len);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a
+    };
+    let lcm = out_len * in_len / gcd;
+
+    let mut out = vec![0u8; out_len];
+    let mut carry: u32 = 0;
+
+    for i in (0..lcm).rev() {
+        // The most significant bit of this byte, within the rotated repetition of the input.
+        let msbit =
+            ((in_bits - 1) + ((in_bits + 13) * (i / in_len)) + ((in_len - (i % in_len)) << 3))
+                % in_bits;
+
+        let hi = input[((in_len - 1) - (msbit >> 3)) % in_len] as u32;
+        let lo = input[(in_len - (msbit >> 3)) % in_len] as u32;
+        carry += (((hi << 8) | lo) >> ((msbit & 7) + 1)) & 0xff;
+        carry += out[i % out_len] as u32;
+        out[i % out_len] = (carry & 0xff) as u8;
+        carry >>= 8;
+    }
+
+    if carry != 0 {
+        for byte in out.iter_mut().rev() {
+            carry += *byte as u32;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+    }
+
+    out
+}
+
+fn aes_block(key: &[u8], block: &[u8], mode: Mode) -> Result<Vec<u8>, CryptoError> {
+    let cipher = match key.len() {
+        16 => Cipher::aes_128_ecb(),
+        32 => Cipher::aes_256_ecb(),
+        _ => return Err(CryptoError::KerberosKeyInvalid),
+    };
+
+    let mut crypter = Crypter::new(cipher, mode, key, None)?;
+    crypter.pad(false);
+
+    let mut out = vec![0; AES_BLOCK_LEN * 2];
+    let count = crypter.update(block, &mut out)?;
+    let rest = crypter.finalize(&mut out[count..])?;
+    out.truncate(count + rest);
+
+    Ok(out)
+}
+
+fn xor_block(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b.iter()).map(|(x, y)| x ^ y).collect()
+}
+
+/// AES in CBC mode with ciphertext stealing and a zero IV, where the last two blocks are
+/// always swapped (RFC 3962, section 5).
+fn aes_cts_encrypt(key: &[u8], data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if data.len() < AES_BLOCK_LEN {
+        return Err(CryptoError::KerberosCiphertextInvalid);
+    }
+
+    if data.len() == AES_BLOCK_LEN {
+        return aes_block(key, data, Mode::Encrypt);
+    }
+
+    let n = data.len().div_ceil(AES_BLOCK_LEN);
+    let last_len = data.len() - (n - 1) * AES_BLOCK_LEN;
+
+    let mut padded = data.to_vec();
+    padded.resize(n * AES_BLOCK_LEN, 0);
+
+    let mut blocks: Vec<Vec<u8>> = Vec::with_capacity(n);
+    let mut prev = vec![0; AES_BLOCK_LEN];
+    for chunk in padded.chunks(AES_BLOCK_LEN) {
+        let block = aes_block(key, &xor_block(chunk, &prev), Mode::Encrypt)?;
+        prev.clone_from(&block);
+        blocks.push(block);
+    }
+
+    let mut out: Vec<u8> = blocks[..n - 2].concat();
+    out.extend_from_slice(&blocks[n - 1]);
+    out.extend_from_slice(&blocks[n - 2][..last_len]);
+
+    Ok(out)
+}
+
+fn aes_cts_decrypt(key: &[u8], data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if data.len() < AES_BLOCK_LEN {
+        return Err(CryptoError::KerberosCiphertextInvalid);
+    }
+
+    if data.len() == AES_BLOCK_LEN {
+        return aes_block(key, data, Mode::Decrypt);
+    }
+
+    let n = data.len().div_ceil(AES_BLOCK_LEN);
+    let last_len = data.len() - (n - 1) * AES_BLOCK_LEN;
+
+    let stolen = &data[(n - 2) * AES_BLOCK_LEN..(n - 1) * AES_BLOCK_LEN];
+    let partial = &data[(n - 1) * AES_BLOCK_LEN..];
+
+    // The stolen block decrypts to the final plaintext, masked by the truncated block, and
+    // the bytes that were stolen from the truncated block.
+    let decrypted = aes_block(key, stolen, Mode::Decrypt)?;
+    let last_plaintext = xor_block(&decrypted[..last_len], partial);
+
+    let mut penultimate = partial.to_vec();
+    penultimate.extend_from_slice(&decrypted[last_len..]);
+
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev = vec![0; AES_BLOCK_LEN];
+    for block in data[..(n - 2) * AES_BLOCK_LEN]
+        .chunks(AES_BLOCK_LEN)
+        .chain(std::iter::once(penultimate.as_slice()))
+    {
+        let decrypted = aes_block(key, block, Mode::Decrypt)?;
+        out.extend_from_slice(&xor_block(&decrypted, &prev));
+        prev = block.to_vec();
+    }
+    out.extend_from_slice(&last_plaintext);
+
+    Ok(out)
+}
+
+fn der_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let len = content.len().to_be_bytes();
+        let start = len.iter().position(|b| *b != 0).unwrap_or(len.len() - 1);
+        out.push(0x80 | (len.len() - start) as u8);
+        out.extend_from_slice(&len[start..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn der_sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    der_tlv(0x30, &items.concat())
+}
+
+fn der_context(tag: u8, content: &[u8]) -> Vec<u8> {
+    der_tlv(0xA0 | tag, content)
+}
+
+fn der_application(tag: u8, content: &[u8]) -> Vec<u8> {
+    der_tlv(0x60 | tag, content)
+}
+
+fn der_integer(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    // Remove redundant leading bytes, while preserving the sign bit.
+    let mut start = 0;
+    while start < bytes.len() - 1 {
+        let redundant = (bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0);
+        if !redundant {
+            break;
+        }
+        start += 1;
+    }
+    der_tlv(0x02, &bytes[start..])
+}
+
+fn der_octet_string(value: &[u8]) -> Vec<u8> {
+    der_tlv(0x04, value)
+}
+
+fn der_general_string(value: &str) -> Vec<u8> {
+    der_tlv(0x1B, value.as_bytes())
+}
+
+fn der_flags(flags: u32) -> Vec<u8> {
+    let mut content = vec![0];
+    content.extend_from_slice(&flags.to_be_bytes());
+    der_tlv(0x03, &content)
+}
+
+fn der_principal_name(principal: &KerberosPrincipal) -> Vec<u8> {
+    let components: Vec<Vec<u8>> = principal
+        .components
+        .iter()
+        .map(|component| der_general_string(component))
+        .collect();
+
+    der_sequence(&[
+        der_context(0, &der_integer(principal.name_type as i64)),
+        der_context(1, &der_sequence(&components)),
+    ])
+}
+
+fn der_encryption_key(key: &[u8]) -> Vec<u8> {
+    der_sequence(&[
+        der_context(0, &der_integer(KRB5_ENCTYPE_AES256_CTS_HMAC_SHA1_96 as i64)),
+        der_context(1, &der_octet_string(key)),
+    ])
+}
+
+/// KerberosTime, a GeneralizedTime in UTC without fractional seconds.
+fn der_time(unix_time: u64) -> Vec<u8> {
+    let days = (unix_time / 86400) as i64;
+    let secs = unix_time % 86400;
+
+    // Convert days since the epoch to a civil date.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let time = format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    );
+
+    der_tlv(0x18, time.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        aes_cts_decrypt, aes_cts_encrypt, der_integer, der_time, kerberos_ccache, kerberos_decrypt,
+        kerberos_derive_service_key, kerberos_encrypt, kerberos_generate_key,
+        kerberos_issue_ticket, kerberos_keytab, nfold, KerberosPrincipal, KerberosServiceKey,
+        KEY_USAGE_TICKET,
+    };
+
+    /// Read a single TLV, returning the tag, content and the remaining input.
+    fn der_read(input: &[u8]) -> (u8, &[u8], &[u8]) {
+        let (len, header) = if input[1] & 0x80 == 0 {
+            (input[1] as usize, 2)
+        } else {
+            let len_len = (input[1] & 0x7f) as usize;
+            let len = input[2..2 + len_len]
+                .iter()
+                .fold(0, |acc, b| (acc << 8) | *b as usize);
+            (len, 2 + len_len)
+        };
+        (
+            input[0],
+            &input[header..header + len],
+            &input[header + len..],
+        )
+    }
+
+    fn der_fields(mut input: &[u8]) -> Vec<(u8, &[u8])> {
+        let mut fields = Vec::new();
+        while !input.is_empty() {
+            let (tag, content, rest) = der_read(input);
+            fields.push((tag, content));
+            input = rest;
+        }
+        fields
+    }
+
+    #[test]
+    fn test_kerberos_nfold() {
+        // RFC 3961, appendix A.1
+        assert_eq!(hex::encode(nfold(b"012345", 8)), "be072631276b1955");
+        assert_eq!(hex::encode(nfold(b"password", 7)), "78a07b6caf85fa");
+        assert_eq!(
+            hex::encode(nfold(b"Rough Consensus, and Running Code", 8)),
+            "bb6ed30870b7f0e0"
+        );
+        assert_eq!(
+            hex::encode(nfold(b"password", 21)),
+            "59e4a8ca7c0385c3c37b3f6d2000247cb6e6bd5b3e"
+        );
+        assert_eq!(
+            hex::encode(nfold(b"kerberos", 16)),
+            "6b65726265726f737b9b5b2b93132b93"
+        );
+    }
+
+    #[test]
+    fn test_kerberos_aes_cts() {
+        // RFC 3962, appendix B
+        let key = b"chicken teriyaki";
+
+        for (plaintext, ciphertext) in [
+            (
+                "4920776f756c64206c696b652074686520",
+                "c6353568f2bf8cb4d8a580362da7ff7f97",
+            ),
+            (
+                "4920776f756c64206c696b65207468652047656e6572616c20476175277320",
+                "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+            ),
+            (
+                "4920776f756c64206c696b65207468652047656e6572616c2047617527732043",
+                "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+            ),
+        ] {
+            let plaintext = hex::decode(plaintext).expect("Invalid test vector");
+            let encrypted = aes_cts_encrypt(key, &plaintext).expect("Unable to encrypt");
+            assert_eq!(hex::encode(&encrypted), ciphertext);
+
+            let decrypted = aes_cts_decrypt(key, &encrypted).expect("Unable to decrypt");
+            assert_eq!(decrypted, plaintext);
+        }
+    }
+
+    #[test]
+    fn test_kerberos_encrypt_decrypt() {
+        let key = kerberos_generate_key();
+        let plaintext = b"kanidm kerberos ticket";
+
+        let ciphertext =
+            kerberos_encrypt(&key, KEY_USAGE_TICKET, plaintext).expect("Unable to encrypt");
+        let decrypted =
+            kerberos_decrypt(&key, KEY_USAGE_TICKET, &ciphertext).expect("Unable to decrypt");
+        assert_eq!(decrypted, plaintext);
+
+        // A different usage derives different keys.
+        assert!(kerberos_decrypt(&key, KEY_USAGE_TICKET + 1, &ciphertext).is_err());
+
+        let mut tampered = ciphertext.clone();
+        tampered[0] ^= 0x01;
+        assert!(kerberos_decrypt(&key, KEY_USAGE_TICKET, &tampered).is_err());
+    }
+
+    #[test]
+    fn test_kerberos_der() {
+        assert_eq!(der_integer(5), vec![0x02, 0x01, 0x05]);
+        assert_eq!(der_integer(128), vec![0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(der_integer(-1), vec![0x02, 0x01, 0xff]);
+
+        assert_eq!(der_time(0)[2..], *b"19700101000000Z");
+        assert_eq!(der_time(1_700_000_000)[2..], *b"20231114221320Z");
+    }
+
+    #[test]
+    fn test_kerberos_issue_ticket() {
+        let client = KerberosPrincipal::user("testaccount", "EXAMPLE.COM");
+        let service =
+            KerberosPrincipal::service("nfs/files.example.com", "EXAMPLE.COM").expect("Invalid");
+        assert_eq!(service.to_string(), "nfs/files.example.com@EXAMPLE.COM");
+
+        assert!(KerberosPrincipal::service("nfs", "EXAMPLE.COM").is_none());
+        assert!(KerberosPrincipal::service("nfs/files/extra", "EXAMPLE.COM").is_none());
+
+        let secret = kerberos_generate_key();
+        let key = kerberos_derive_service_key(&secret, &service).expect("Unable to derive");
+        assert_eq!(key.len(), 32);
+        // The key is stable for the same secret and service.
+        assert_eq!(
+            key,
+            kerberos_derive_service_key(&secret, &service).expect("Unable to derive")
+        );
+
+        let service_key = KerberosServiceKey { kvno: 7, key };
+
+        let credential = kerberos_issue_ticket(
+            &client,
+            &service,
+            &service_key,
+            1_700_000_000,
+            1_700_036_000,
+        )
+        .expect("Unable to issue ticket");
+
+        assert_eq!(credential.session_key.len(), 32);
+
+        // Ticket ::= [APPLICATION 1] SEQUENCE { .., enc-part [3] EncryptedData }
+        let (tag, ticket, _) = der_read(&credential.ticket);
+        assert_eq!(tag, 0x61);
+        let (_, ticket, _) = der_read(ticket);
+        let enc_part = der_fields(ticket)
+            .into_iter()
+            .find(|(tag, _)| *tag == 0xA3)
+            .map(|(_, enc_part)| der_read(enc_part).1)
+            .expect("No enc-part");
+        let cipher = der_fields(enc_part)
+            .into_iter()
+            .find(|(tag, _)| *tag == 0xA2)
+            .map(|(_, cipher)| der_read(cipher).1)
+            .expect("No cipher");
+
+        let enc_ticket_part = kerberos_decrypt(&service_key.key, KEY_USAGE_TICKET, cipher)
+            .expect("Unable to decrypt ticket");
+        // EncTicketPart ::= [APPLICATION 3]
+        assert_eq!(enc_ticket_part[0], 0x63);
+        assert!(enc_ticket_part
+            .windows(credential.session_key.len())
+            .any(|w| w == credential.session_key.as_slice()));
+
+        let keytab = kerberos_keytab(&[(service.clone(), service_key)]);
+        assert_eq!(keytab[..2], [0x05, 0x02]);
+
+        let ccache = kerberos_ccache(&[credential]);
+        assert_eq!(ccache[..2], [0x05, 0x04]);
+    }
+}
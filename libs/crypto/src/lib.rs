@@ -28,11 +28,16 @@ use std::fmt;
 use std::time::{Duration, Instant};
 use tracing::{debug, error, trace, warn};
 
+mod bcrypt;
 mod crypt_md5;
+pub mod kerberos;
 pub mod mtls;
 pub mod prelude;
+pub mod saml2;
 pub mod serialise;
+pub mod ssh_ca;
 pub mod x509_cert;
+mod yescrypt;
 
 pub use sha2;
 
@@ -83,6 +88,12 @@ pub enum CryptoError {
     Argon2Version,
     Argon2Parameters,
     Crypt,
+    CertificateRequestInvalid,
+    SshPublicKeyInvalid,
+    SshCertificateAuthorityInvalid,
+    Saml2SigningKeyInvalid,
+    KerberosKeyInvalid,
+    KerberosCiphertextInvalid,
 }
 
 impl From<OpenSSLErrorStack> for CryptoError {
@@ -146,6 +157,12 @@ pub enum DbPasswordV1 {
     CRYPT_SHA512 {
         h: String,
     },
+    CRYPT_BCRYPT {
+        h: String,
+    },
+    CRYPT_YESCRYPT {
+        h: String,
+    },
 }
 
 impl fmt::Debug for DbPasswordV1 {
@@ -166,6 +183,8 @@ impl fmt::Debug for DbPasswordV1 {
             DbPasswordV1::CRYPT_MD5 { .. } => write!(f, "CRYPT_MD5"),
             DbPasswordV1::CRYPT_SHA256 { .. } => write!(f, "CRYPT_SHA256"),
             DbPasswordV1::CRYPT_SHA512 { .. } => write!(f, "CRYPT_SHA512"),
+            DbPasswordV1::CRYPT_BCRYPT { .. } => write!(f, "CRYPT_BCRYPT"),
+            DbPasswordV1::CRYPT_YESCRYPT { .. } => write!(f, "CRYPT_YESCRYPT"),
         }
     }
 }
@@ -176,6 +195,8 @@ pub struct CryptoPolicy {
     // https://docs.rs/argon2/0.5.0/argon2/struct.Params.html
     // defaults to 19mb memory, 2 iterations and 1 thread, with a 32byte output.
     pub(crate) argon2id_params: Params,
+    // The time that the parameters were benchmarked to meet, if any.
+    pub(crate) target_time: Option<Duration>,
 }
 
 impl CryptoPolicy {
@@ -183,6 +204,7 @@ impl CryptoPolicy {
         CryptoPolicy {
             pbkdf2_cost: PBKDF2_MIN_NIST_COST,
             argon2id_params: Params::default(),
+            target_time: None,
         }
     }
 
@@ -196,6 +218,7 @@ impl CryptoPolicy {
                 None,
             )
             .unwrap_or_default(),
+            target_time: None,
         }
     }
 
@@ -342,10 +365,23 @@ impl CryptoPolicy {
         let p = CryptoPolicy {
             pbkdf2_cost,
             argon2id_params,
+            target_time: Some(target_time),
         };
         debug!(pbkdf2_cost = %p.pbkdf2_cost, argon2id_m = %p.argon2id_params.m_cost(), argon2id_p = %p.argon2id_params.p_cost(), argon2id_t = %p.argon2id_params.t_cost(), );
         p
     }
+
+    /// The time a single key derivation was benchmarked to take when this policy
+    /// was created, if it was benchmarked.
+    pub fn target_time(&self) -> Option<Duration> {
+        self.target_time
+    }
+
+    /// Measure the time a single argon2id key derivation takes with the parameters
+    /// of this policy on the current system.
+    pub fn bench_argon2id(&self) -> Option<Duration> {
+        Password::bench_argon2id(self.argon2id_params.clone())
+    }
 }
 
 // Why PBKDF2? Rust's bcrypt has a number of hardcodings like max pw len of 72
@@ -398,6 +434,12 @@ enum Kdf {
     CRYPT_SHA512 {
         h: String,
     },
+    CRYPT_BCRYPT {
+        h: String,
+    },
+    CRYPT_YESCRYPT {
+        h: String,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -470,7 +512,13 @@ impl TryFrom<DbPasswordV1> for Password {
                 material: Kdf::CRYPT_SHA256 { h },
             }),
             DbPasswordV1::CRYPT_SHA512 { h } => Ok(Password {
-                material: Kdf::CRYPT_SHA256 { h },
+                material: Kdf::CRYPT_SHA512 { h },
+            }),
+            DbPasswordV1::CRYPT_BCRYPT { h } => Ok(Password {
+                material: Kdf::CRYPT_BCRYPT { h },
+            }),
+            DbPasswordV1::CRYPT_YESCRYPT { h } => Ok(Password {
+                material: Kdf::CRYPT_YESCRYPT { h },
             }),
         }
     }
@@ -564,11 +612,12 @@ impl TryFrom<&str> for Password {
         }
 
         // Test 389ds/openldap formats. Shout outs openldap which sometimes makes these
-        // lowercase.
+        // lowercase. Hashes taken from a shadow file or other crypt(3) users have no prefix.
 
         if let Some(crypt) = value
             .strip_prefix("{crypt}")
             .or_else(|| value.strip_prefix("{CRYPT}"))
+            .or_else(|| value.starts_with('$').then_some(value))
         {
             if let Some(crypt_md5_phc) = crypt.strip_prefix("$1$") {
                 let (salt, hash) = crypt_md5_phc.split_once('$').ok_or(())?;
@@ -598,6 +647,46 @@ impl TryFrom<&str> for Password {
                     },
                 });
             }
+
+            if crypt.starts_with("$2a$") || crypt.starts_with("$2b$") || crypt.starts_with("$2y$") {
+                if bcrypt::BcryptHash::parse(crypt).is_none() {
+                    error!("Invalid bcrypt hash");
+                    return Err(());
+                }
+                return Ok(Password {
+                    material: Kdf::CRYPT_BCRYPT {
+                        h: crypt.to_string(),
+                    },
+                });
+            }
+
+            // FreeBSD and John the Ripper store NT hashes as a crypt string.
+            if let Some(nt_md4) = crypt
+                .strip_prefix("$3$$")
+                .or_else(|| crypt.strip_prefix("$NT$"))
+            {
+                let h = hex::decode(nt_md4).map_err(|_| ())?;
+                return Ok(Password {
+                    material: Kdf::NT_MD4(h),
+                });
+            }
+
+            if crypt.starts_with("$y$") {
+                if yescrypt::YescryptHash::parse(crypt).is_none() {
+                    error!("Invalid or unsupported yescrypt hash");
+                    return Err(());
+                }
+                return Ok(Password {
+                    material: Kdf::CRYPT_YESCRYPT {
+                        h: crypt.to_string(),
+                    },
+                });
+            }
+
+            if crypt.starts_with("$gy$") || crypt.starts_with("$7$") {
+                error!("gost-yescrypt and scrypt password hashes are not supported");
+                return Err(());
+            }
         } // End crypt
 
         if let Some(ds_ssha1) = value
@@ -761,7 +850,12 @@ impl TryFrom<&str> for Password {
             }
         }
 
-        if let Some(argon2_phc) = value.strip_prefix("{ARGON2}") {
+        // Argon2id hashes may have parameters that differ from the ones we would create, which
+        // are kept so that the hash can still be verified.
+        if let Some(argon2_phc) = value
+            .strip_prefix("{ARGON2}")
+            .or_else(|| value.starts_with("$argon2").then_some(value))
+        {
             match PasswordHash::try_from(argon2_phc) {
                 Ok(PasswordHash {
                     algorithm,
@@ -1191,6 +1285,17 @@ impl Password {
 
                 Ok(is_valid)
             }
+            (Kdf::CRYPT_BCRYPT { h }, _) => {
+                let is_valid = bcrypt::BcryptHash::parse(h)
+                    .map(|bcrypt_hash| bcrypt_hash.verify(cleartext.as_bytes()))
+                    .unwrap_or_default();
+
+                Ok(is_valid)
+            }
+            (Kdf::CRYPT_YESCRYPT { h }, _) => match yescrypt::YescryptHash::parse(h) {
+                Some(yescrypt_hash) => yescrypt_hash.verify(cleartext.as_bytes()),
+                None => Ok(false),
+            },
         }
     }
 
@@ -1248,9 +1353,16 @@ impl Password {
             },
             Kdf::CRYPT_SHA256 { h } => DbPasswordV1::CRYPT_SHA256 { h: h.clone() },
             Kdf::CRYPT_SHA512 { h } => DbPasswordV1::CRYPT_SHA512 { h: h.clone() },
+            Kdf::CRYPT_BCRYPT { h } => DbPasswordV1::CRYPT_BCRYPT { h: h.clone() },
+            Kdf::CRYPT_YESCRYPT { h } => DbPasswordV1::CRYPT_YESCRYPT { h: h.clone() },
         }
     }
 
+    /// If this password can only be verified with the hsm key that created it.
+    pub fn is_hsm_bound(&self) -> bool {
+        matches!(self.material, Kdf::TPM_ARGON2ID { .. })
+    }
+
     pub fn requires_upgrade(&self) -> bool {
         match &self.material {
             Kdf::ARGON2ID {
@@ -1286,7 +1398,9 @@ impl Password {
             | Kdf::NT_MD4(_)
             | Kdf::CRYPT_MD5 { .. }
             | Kdf::CRYPT_SHA256 { .. }
-            | Kdf::CRYPT_SHA512 { .. } => true,
+            | Kdf::CRYPT_SHA512 { .. }
+            | Kdf::CRYPT_BCRYPT { .. }
+            | Kdf::CRYPT_YESCRYPT { .. } => true,
         }
     }
 }
@@ -1575,6 +1689,140 @@ mod tests {
 
         assert!(r.requires_upgrade());
         assert!(r.verify(password).unwrap_or(false));
+
+        // Stored hashes must still verify once loaded from the database.
+        let r = Password::try_from(r.to_dbpasswordv1()).expect("Failed to load");
+        assert!(r.verify(password).unwrap_or(false));
+    }
+
+    #[test]
+    fn test_password_from_crypt_without_prefix() {
+        sketching::test_init();
+        let im_pw = "$1$skuoZ.do$iaUaEqjHHpN5PwzsVDHWp.";
+        let password = "password";
+        let r = Password::try_from(im_pw).expect("Failed to parse");
+
+        assert!(r.requires_upgrade());
+        assert!(r.verify(password).unwrap_or(false));
+    }
+
+    #[test]
+    fn test_password_from_crypt_bcrypt() {
+        sketching::test_init();
+        for (im_pw, password) in [
+            (
+                "$2b$04$abcdefghijklmnopqrstuughE8Ev8uGFaUgY2cNEySvxngrb/Jzdm",
+                "password",
+            ),
+            (
+                "$2y$04$abcdefghijklmnopqrstuughE8Ev8uGFaUgY2cNEySvxngrb/Jzdm",
+                "password",
+            ),
+            (
+                "{crypt}$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW",
+                "U*U",
+            ),
+        ] {
+            let r = Password::try_from(im_pw).expect("Failed to parse");
+            assert!(r.requires_upgrade());
+            let r = Password::try_from(r.to_dbpasswordv1()).expect("Failed to load");
+            assert!(r.verify(password).unwrap_or(false));
+            assert!(!r.verify("password1").unwrap_or(true));
+        }
+
+        // Truncated hashes are rejected on import rather than failing every authentication.
+        assert!(Password::try_from("$2b$04$abcdefghijklmnopqrstuughE8Ev8uG").is_err());
+
+        // Costs above the cap are rejected on import, as every authentication would exhaust the
+        // server.
+        for im_pw in [
+            "$2b$17$abcdefghijklmnopqrstuughE8Ev8uGFaUgY2cNEySvxngrb/Jzdm",
+            "$2y$31$abcdefghijklmnopqrstuughE8Ev8uGFaUgY2cNEySvxngrb/Jzdm",
+            "{crypt}$2a$20$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW",
+        ] {
+            assert!(Password::try_from(im_pw).is_err());
+        }
+
+        // A hash above the cap that was stored before it existed is never verified.
+        let r = Password::try_from(DbPasswordV1::CRYPT_BCRYPT {
+            h: "$2b$31$abcdefghijklmnopqrstuughE8Ev8uGFaUgY2cNEySvxngrb/Jzdm".to_string(),
+        })
+        .expect("Failed to load");
+        assert!(!r.verify("password").unwrap_or(true));
+    }
+
+    #[test]
+    fn test_password_from_crypt_nt_hash() {
+        sketching::test_init();
+        let password = "password";
+        for im_pw in [
+            "$NT$8846f7eaee8fb117ad06bdd830b7586c",
+            "$3$$8846f7eaee8fb117ad06bdd830b7586c",
+        ] {
+            let r = Password::try_from(im_pw).expect("Failed to parse");
+            assert!(r.requires_upgrade());
+            match r.verify(password) {
+                Ok(r) => assert!(r),
+                Err(_) =>
+                {
+                    #[allow(clippy::panic)]
+                    if cfg!(openssl3) {
+                        warn!("To run this test, enable the legacy provider.");
+                    } else {
+                        panic!("OpenSSL3 feature not enabled")
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_password_from_argon2id_custom_params() {
+        sketching::test_init();
+        let password = "password";
+        let salt = b"kanidm import salt";
+        let mut key = [0u8; 24];
+        let params = Params::new(12 * 1024, 3, 1, Some(key.len())).unwrap();
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), salt, &mut key)
+            .unwrap();
+
+        let im_pw = format!(
+            "$argon2id$v=19$m=12288,t=3,p=1${}${}",
+            general_purpose::STANDARD_NO_PAD.encode(salt),
+            general_purpose::STANDARD_NO_PAD.encode(key)
+        );
+        let r = Password::try_from(im_pw.as_str()).expect("Failed to parse");
+        assert!(r.verify(password).unwrap_or(false));
+        assert!(!r.verify("password1").unwrap_or(true));
+        // The key is shorter than we would create.
+        assert!(r.requires_upgrade());
+    }
+
+    #[test]
+    fn test_password_from_yescrypt() {
+        sketching::test_init();
+        for im_pw in [
+            "$y$j9T$F5Jx5fExrKuPp53xLKQ..1$tnSYvahCwPBHKZUspmcxMfb0.WiB9W.zEaKlOBL35rC",
+            "{crypt}$y$j75$saltsaltsaltsalt$hI02SdBpr3mSssvBRd05Dwe0nTFc/hsy01KTxh646J.",
+        ] {
+            let r = Password::try_from(im_pw).expect("Failed to parse");
+            assert!(r.requires_upgrade());
+            let r = Password::try_from(r.to_dbpasswordv1()).expect("Failed to load");
+            assert!(r.verify("password").unwrap_or(false));
+            assert!(!r.verify("password1").unwrap_or(true));
+        }
+
+        // Parameters that would be too expensive to verify are rejected on import.
+        assert!(Password::try_from(
+            "$y$jDT$saltsaltsaltsalt$hI02SdBpr3mSssvBRd05Dwe0nTFc/hsy01KTxh646J."
+        )
+        .is_err());
+        // gost-yescrypt is not supported.
+        assert!(Password::try_from(
+            "$gy$j9T$saltsaltsaltsalt$hI02SdBpr3mSssvBRd05Dwe0nTFc/hsy01KTxh646J."
+        )
+        .is_err());
     }
 
     #[test]
@@ -1597,6 +1845,7 @@ mod tests {
 
         let p = CryptoPolicy::minimum();
         let c = Password::new_argon2id_hsm(&p, "password", ctx, &key).unwrap();
+        assert!(c.is_hsm_bound());
 
         assert!(matches!(
             c.verify("password"),
@@ -1626,6 +1875,7 @@ mod tests {
             _ => unreachable!(),
         };
 
+        assert!(!dup.is_hsm_bound());
         assert!(!dup.verify("password").unwrap());
 
         assert!(c.verify_ctx("password", Some((ctx, &key))).unwrap());
@@ -1,19 +1,20 @@
 use crate::CryptoError;
 
 use openssl::asn1;
-use openssl::bn;
+use openssl::bn::{self, MsbOption};
 use openssl::ec;
 use openssl::error::ErrorStack as OpenSSLError;
 use openssl::hash;
 use openssl::nid::Nid;
 use openssl::pkey::{PKey, Private};
+use openssl::x509::extension::AuthorityKeyIdentifier;
 use openssl::x509::extension::BasicConstraints;
 use openssl::x509::extension::ExtendedKeyUsage;
 use openssl::x509::extension::KeyUsage;
 use openssl::x509::extension::SubjectAlternativeName;
 use openssl::x509::extension::SubjectKeyIdentifier;
 use openssl::x509::X509NameBuilder;
-use openssl::x509::X509;
+use openssl::x509::{X509Req, X509VerifyResult, X509};
 
 use uuid::Uuid;
 
@@ -87,3 +88,271 @@ pub fn build_self_signed_server_and_client_identity(
 
     Ok((ca_key, ca_cert))
 }
+
+/// Build the certificate of a replication trust anchor from its key. The certificate only
+/// depends on the key and the time it is valid from, so every server of a domain that holds
+/// the key can build an equivalent anchor without the certificate itself being replicated.
+/// The anchor has no expiry - it is retired by revoking the key instead.
+pub fn build_replication_trust_anchor(
+    ca_key: &PKey<Private>,
+    key_id: &str,
+    valid_from: i64,
+) -> Result<X509, CryptoError> {
+    let mut x509_name = X509NameBuilder::new()?;
+
+    x509_name.append_entry_by_text("O", "Kanidm Replication Trust Anchor")?;
+    x509_name.append_entry_by_text("CN", key_id)?;
+    let x509_name = x509_name.build();
+
+    let mut cert_builder = X509::builder()?;
+    cert_builder.set_version(2)?;
+
+    let serial_number = bn::BigNum::from_u32(1).and_then(|serial| serial.to_asn1_integer())?;
+
+    cert_builder.set_serial_number(&serial_number)?;
+    cert_builder.set_subject_name(&x509_name)?;
+    cert_builder.set_issuer_name(&x509_name)?;
+
+    let not_before = asn1::Asn1Time::from_unix(valid_from)?;
+    cert_builder.set_not_before(&not_before)?;
+    // The value defined for "no well-defined expiration date" by RFC 5280.
+    let not_after = asn1::Asn1Time::from_str_x509("99991231235959Z")?;
+    cert_builder.set_not_after(&not_after)?;
+
+    // The anchor may only issue the identities of servers.
+    cert_builder.append_extension(BasicConstraints::new().critical().ca().pathlen(0).build()?)?;
+    cert_builder.append_extension(
+        KeyUsage::new()
+            .critical()
+            .key_cert_sign()
+            .crl_sign()
+            .build()?,
+    )?;
+
+    let subject_key_identifier =
+        SubjectKeyIdentifier::new().build(&cert_builder.x509v3_context(None, None))?;
+    cert_builder.append_extension(subject_key_identifier)?;
+
+    cert_builder.set_pubkey(ca_key)?;
+
+    cert_builder.sign(ca_key, hash::MessageDigest::sha256())?;
+
+    Ok(cert_builder.build())
+}
+
+/// Issue the replication identity of a server from a replication trust anchor. Other servers
+/// that trust the anchor will accept this identity for both the client and server side of
+/// replication connections.
+pub fn build_replication_identity(
+    ca_key: &PKey<Private>,
+    ca_cert: &X509,
+    cn: Uuid,
+    domain_name: &str,
+    expiration_days: u32,
+) -> Result<(PKey<Private>, X509), CryptoError> {
+    let ecgroup = get_group()?;
+    let eckey = ec::EcKey::generate(&ecgroup)?;
+    let key = PKey::from_ec_key(eckey)?;
+    let mut x509_name = X509NameBuilder::new()?;
+
+    x509_name.append_entry_by_text("O", "Kanidm Replication")?;
+    x509_name.append_entry_by_text("CN", &cn.as_hyphenated().to_string())?;
+    let x509_name = x509_name.build();
+
+    let mut cert_builder = X509::builder()?;
+    cert_builder.set_version(2)?;
+
+    let mut serial = bn::BigNum::new()?;
+    serial.rand(127, MsbOption::MAYBE_ZERO, false)?;
+    let serial_number = serial.to_asn1_integer()?;
+
+    cert_builder.set_serial_number(&serial_number)?;
+    cert_builder.set_subject_name(&x509_name)?;
+    cert_builder.set_issuer_name(ca_cert.subject_name())?;
+
+    let not_before = asn1::Asn1Time::days_from_now(0)?;
+    cert_builder.set_not_before(&not_before)?;
+    // The identity can't outlive the anchor that issued it.
+    let not_after = asn1::Asn1Time::days_from_now(expiration_days)?;
+    if not_after < *ca_cert.not_after() {
+        cert_builder.set_not_after(&not_after)?;
+    } else {
+        cert_builder.set_not_after(ca_cert.not_after())?;
+    }
+
+    cert_builder.append_extension(BasicConstraints::new().critical().build()?)?;
+    cert_builder.append_extension(
+        KeyUsage::new()
+            .critical()
+            .digital_signature()
+            .key_encipherment()
+            .build()?,
+    )?;
+
+    cert_builder.append_extension(
+        ExtendedKeyUsage::new()
+            .server_auth()
+            .client_auth()
+            .build()?,
+    )?;
+
+    let subject_key_identifier =
+        SubjectKeyIdentifier::new().build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
+    cert_builder.append_extension(subject_key_identifier)?;
+
+    let authority_key_identifier = AuthorityKeyIdentifier::new()
+        .keyid(false)
+        .build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
+    cert_builder.append_extension(authority_key_identifier)?;
+
+    let subject_alt_name = SubjectAlternativeName::new()
+        .dns(domain_name)
+        .build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
+    cert_builder.append_extension(subject_alt_name)?;
+
+    cert_builder.set_pubkey(&key)?;
+
+    cert_builder.sign(ca_key, hash::MessageDigest::sha256())?;
+
+    Ok((key, cert_builder.build()))
+}
+
+/// Check if a certificate was issued and signed by the certificate authority `ca_cert`.
+pub fn is_issued_by(cert: &X509, ca_cert: &X509) -> bool {
+    ca_cert.issued(cert) == X509VerifyResult::OK
+        && ca_cert
+            .public_key()
+            .and_then(|ca_public| cert.verify(&ca_public))
+            .unwrap_or(false)
+}
+
+/// Check if a certificate expires within the next `days`.
+pub fn expires_within_days(cert: &X509, days: u32) -> Result<bool, CryptoError> {
+    let threshold = asn1::Asn1Time::days_from_now(days)?;
+    Ok(*cert.not_after() < threshold)
+}
+
+/// Build a self signed certificate authority that is able to issue client certificates,
+/// such as those used for EAP-TLS.
+pub fn build_self_signed_client_ca(
+    cn: Uuid,
+    domain_name: &str,
+    expiration_days: u32,
+) -> Result<(PKey<Private>, X509), CryptoError> {
+    let ecgroup = get_group()?;
+    let eckey = ec::EcKey::generate(&ecgroup)?;
+    let ca_key = PKey::from_ec_key(eckey)?;
+    let mut x509_name = X509NameBuilder::new()?;
+
+    x509_name.append_entry_by_text("O", "Kanidm Client Certificate Authority")?;
+    x509_name.append_entry_by_text("OU", domain_name)?;
+    x509_name.append_entry_by_text("CN", &cn.as_hyphenated().to_string())?;
+    let x509_name = x509_name.build();
+
+    let mut cert_builder = X509::builder()?;
+    cert_builder.set_version(2)?;
+
+    let serial_number = bn::BigNum::from_u32(1).and_then(|serial| serial.to_asn1_integer())?;
+
+    cert_builder.set_serial_number(&serial_number)?;
+    cert_builder.set_subject_name(&x509_name)?;
+    cert_builder.set_issuer_name(&x509_name)?;
+
+    let not_before = asn1::Asn1Time::days_from_now(0)?;
+    cert_builder.set_not_before(&not_before)?;
+    let not_after = asn1::Asn1Time::days_from_now(expiration_days)?;
+    cert_builder.set_not_after(&not_after)?;
+
+    // This CA may only issue leaf certificates.
+    cert_builder.append_extension(BasicConstraints::new().critical().ca().pathlen(0).build()?)?;
+    cert_builder.append_extension(
+        KeyUsage::new()
+            .critical()
+            .key_cert_sign()
+            .crl_sign()
+            .build()?,
+    )?;
+
+    let subject_key_identifier =
+        SubjectKeyIdentifier::new().build(&cert_builder.x509v3_context(None, None))?;
+    cert_builder.append_extension(subject_key_identifier)?;
+
+    cert_builder.set_pubkey(&ca_key)?;
+
+    cert_builder.sign(&ca_key, hash::MessageDigest::sha256())?;
+    let ca_cert = cert_builder.build();
+
+    Ok((ca_key, ca_cert))
+}
+
+/// Sign a certificate signing request, issuing a client certificate for the subject. The
+/// subject of the request is ignored - the certificate common name is always the uuid of
+/// the subject, so that the certificate can't be used to claim another identity.
+pub fn sign_client_certificate_request(
+    ca_key: &PKey<Private>,
+    ca_cert: &X509,
+    csr: &X509Req,
+    subject: Uuid,
+    spn: &str,
+    expiration_days: u32,
+) -> Result<X509, CryptoError> {
+    let csr_pubkey = csr.public_key()?;
+
+    // The requestor must prove they hold the private key.
+    if !csr.verify(&csr_pubkey)? {
+        return Err(CryptoError::CertificateRequestInvalid);
+    }
+
+    let mut x509_name = X509NameBuilder::new()?;
+    x509_name.append_entry_by_text("CN", &subject.as_hyphenated().to_string())?;
+    let x509_name = x509_name.build();
+
+    let mut cert_builder = X509::builder()?;
+    cert_builder.set_version(2)?;
+
+    // Serial numbers must be unique for the issuer, and are used to identify the
+    // certificate during authentication.
+    let mut serial = bn::BigNum::new()?;
+    serial.rand(127, MsbOption::MAYBE_ZERO, false)?;
+    let serial_number = serial.to_asn1_integer()?;
+
+    cert_builder.set_serial_number(&serial_number)?;
+    cert_builder.set_subject_name(&x509_name)?;
+    cert_builder.set_issuer_name(ca_cert.subject_name())?;
+
+    let not_before = asn1::Asn1Time::days_from_now(0)?;
+    cert_builder.set_not_before(&not_before)?;
+    let not_after = asn1::Asn1Time::days_from_now(expiration_days)?;
+    cert_builder.set_not_after(&not_after)?;
+
+    cert_builder.append_extension(BasicConstraints::new().critical().build()?)?;
+    cert_builder.append_extension(
+        KeyUsage::new()
+            .critical()
+            .digital_signature()
+            .key_agreement()
+            .build()?,
+    )?;
+    cert_builder.append_extension(ExtendedKeyUsage::new().client_auth().build()?)?;
+
+    let subject_key_identifier =
+        SubjectKeyIdentifier::new().build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
+    cert_builder.append_extension(subject_key_identifier)?;
+
+    let authority_key_identifier = AuthorityKeyIdentifier::new()
+        .keyid(false)
+        .build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
+    cert_builder.append_extension(authority_key_identifier)?;
+
+    let subject_alt_name = SubjectAlternativeName::new()
+        .email(spn)
+        .uri(&format!("urn:uuid:{}", subject.as_hyphenated()))
+        .build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
+    cert_builder.append_extension(subject_alt_name)?;
+
+    cert_builder.set_pubkey(&csr_pubkey)?;
+
+    cert_builder.sign(ca_key, hash::MessageDigest::sha256())?;
+
+    Ok(cert_builder.build())
+}
@@ -0,0 +1,117 @@
+//! The signing keys of a SAML identity provider. Service providers are configured to trust
+//! the certificate of the key from the metadata of the identity provider, and many of them
+//! pin the certificate itself rather than the key. RSA PKCS#1 v1.5 signatures are
+//! deterministic, so the certificate is the same every time it is built from the key.
+
+use crate::CryptoError;
+
+use openssl::asn1;
+use openssl::bn;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::sign::{Signer, Verifier};
+use openssl::x509::extension::{BasicConstraints, KeyUsage};
+use openssl::x509::{X509NameBuilder, X509};
+
+const SAML2_SIGNING_KEY_BITS: u32 = 3072;
+
+/// Generate a new key to sign SAML assertions with.
+pub fn generate_saml2_signing_key() -> Result<PKey<Private>, CryptoError> {
+    Rsa::generate(SAML2_SIGNING_KEY_BITS)
+        .and_then(PKey::from_rsa)
+        .map_err(CryptoError::from)
+}
+
+/// Build the self signed certificate of a SAML signing key. The certificate only depends on
+/// the key and the time it is valid from, so it does not need to be stored or replicated.
+pub fn build_saml2_signing_certificate(
+    signing_key: &PKey<Private>,
+    key_id: &str,
+    valid_from: i64,
+) -> Result<X509, CryptoError> {
+    if signing_key.rsa().is_err() {
+        return Err(CryptoError::Saml2SigningKeyInvalid);
+    }
+
+    let mut x509_name = X509NameBuilder::new()?;
+
+    x509_name.append_entry_by_text("O", "Kanidm SAML Signing")?;
+    x509_name.append_entry_by_text("CN", key_id)?;
+    let x509_name = x509_name.build();
+
+    let mut cert_builder = X509::builder()?;
+    cert_builder.set_version(2)?;
+
+    let serial_number = bn::BigNum::from_u32(1).and_then(|serial| serial.to_asn1_integer())?;
+
+    cert_builder.set_serial_number(&serial_number)?;
+    cert_builder.set_subject_name(&x509_name)?;
+    cert_builder.set_issuer_name(&x509_name)?;
+
+    let not_before = asn1::Asn1Time::from_unix(valid_from)?;
+    cert_builder.set_not_before(&not_before)?;
+    // Service providers rarely check the validity of the certificate, and the key is retired
+    // by revoking it instead.
+    let not_after = asn1::Asn1Time::from_str_x509("99991231235959Z")?;
+    cert_builder.set_not_after(&not_after)?;
+
+    cert_builder.append_extension(BasicConstraints::new().critical().build()?)?;
+    cert_builder.append_extension(KeyUsage::new().critical().digital_signature().build()?)?;
+
+    cert_builder.set_pubkey(signing_key)?;
+
+    cert_builder.sign(signing_key, MessageDigest::sha256())?;
+
+    Ok(cert_builder.build())
+}
+
+/// Sign data with rsa-sha256, as used by XML signatures.
+pub fn saml2_sign(signing_key: &PKey<Private>, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut signer = Signer::new(MessageDigest::sha256(), signing_key)?;
+    signer.update(data)?;
+    signer.sign_to_vec().map_err(CryptoError::from)
+}
+
+/// Verify an rsa-sha256 signature with the key of a signing certificate.
+pub fn saml2_verify(certificate: &X509, data: &[u8], signature: &[u8]) -> bool {
+    certificate
+        .public_key()
+        .and_then(|public_key| {
+            let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key)?;
+            verifier.update(data)?;
+            verifier.verify(signature)
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_saml2_signing_certificate, generate_saml2_signing_key, saml2_sign, saml2_verify,
+    };
+
+    #[test]
+    fn test_saml2_signing() {
+        let signing_key = generate_saml2_signing_key().expect("Unable to generate signing key");
+        let other_key = generate_saml2_signing_key().expect("Unable to generate signing key");
+
+        let certificate = build_saml2_signing_certificate(&signing_key, "test", 10)
+            .expect("Unable to build certificate");
+
+        // The certificate must be the same each time it is built.
+        let rebuilt = build_saml2_signing_certificate(&signing_key, "test", 10)
+            .expect("Unable to build certificate");
+        assert_eq!(
+            certificate.to_der().expect("Unable to encode certificate"),
+            rebuilt.to_der().expect("Unable to encode certificate")
+        );
+
+        let signature = saml2_sign(&signing_key, b"assertion").expect("Unable to sign");
+        assert!(saml2_verify(&certificate, b"assertion", &signature));
+        assert!(!saml2_verify(&certificate, b"other assertion", &signature));
+
+        let other_signature = saml2_sign(&other_key, b"assertion").expect("Unable to sign");
+        assert!(!saml2_verify(&certificate, b"assertion", &other_signature));
+    }
+}
@@ -89,3 +89,29 @@ pub mod x509b64 {
         })
     }
 }
+
+pub mod x509b64_option {
+    use super::x509b64;
+    use openssl::x509::X509;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(with = "x509b64")] X509);
+
+    pub fn serialize<S>(cert: &Option<X509>, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match cert {
+            Some(cert) => x509b64::serialize(cert, ser),
+            None => ser.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(des: D) -> Result<Option<X509>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<Wrapper>::deserialize(des).map(|maybe_cert| maybe_cert.map(|Wrapper(cert)| cert))
+    }
+}
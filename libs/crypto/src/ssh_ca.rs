@@ -0,0 +1,329 @@
+//! Issuance of OpenSSH user certificates. The certificate format is described by
+//! PROTOCOL.certkeys in the OpenSSH source. The certificate authority is always an
+//! ecdsa-sha2-nistp256 key, but any type of user key may be certified.
+
+use crate::CryptoError;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::PointConversionForm;
+use openssl::ecdsa::EcdsaSig;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::sha::sha256;
+use rand::Rng;
+
+const SSH_CA_KEY_TYPE: &str = "ecdsa-sha2-nistp256";
+const SSH_CA_CURVE: &str = "nistp256";
+const SSH_CERT_TYPE_USER: u32 = 1;
+
+/// The extensions granted to user certificates. These are the same as the defaults of
+/// ssh-keygen, and must remain sorted by name.
+const SSH_USER_CERTIFICATE_EXTENSIONS: [&str; 5] = [
+    "permit-X11-forwarding",
+    "permit-agent-forwarding",
+    "permit-port-forwarding",
+    "permit-pty",
+    "permit-user-rc",
+];
+
+/// Encodes values in the ssh wire format (RFC 4251, section 5).
+#[derive(Default)]
+struct SshWriter(Vec<u8>);
+
+impl SshWriter {
+    fn raw(&mut self, value: &[u8]) {
+        self.0.extend_from_slice(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.raw(&value.to_be_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.raw(&value.to_be_bytes());
+    }
+
+    fn string(&mut self, value: &[u8]) {
+        self.u32(value.len() as u32);
+        self.raw(value);
+    }
+
+    /// Write an unsigned big endian integer as an mpint.
+    fn mpint(&mut self, value: &[u8]) {
+        let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+        let value = &value[start..];
+        // A leading zero is required to keep the value positive.
+        if value.first().is_some_and(|b| b & 0x80 != 0) {
+            self.u32(value.len() as u32 + 1);
+            self.raw(&[0]);
+            self.raw(value);
+        } else {
+            self.string(value);
+        }
+    }
+}
+
+/// Decodes values in the ssh wire format.
+struct SshReader<'a>(&'a [u8]);
+
+impl<'a> SshReader<'a> {
+    fn string(&mut self) -> Option<&'a [u8]> {
+        let len = self
+            .0
+            .get(..4)
+            .and_then(|len| len.try_into().ok())
+            .map(u32::from_be_bytes)? as usize;
+        let value = self.0.get(4..4 + len)?;
+        self.0 = &self.0[4 + len..];
+        Some(value)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+fn ssh_ca_public_key_blob(ca_key: &PKey<Private>) -> Result<Vec<u8>, CryptoError> {
+    let ec_key = ca_key.ec_key()?;
+
+    if ec_key.group().curve_name() != Some(Nid::X9_62_PRIME256V1) {
+        return Err(CryptoError::SshCertificateAuthorityInvalid);
+    }
+
+    let mut ctx = BigNumContext::new()?;
+    let point = ec_key.public_key().to_bytes(
+        ec_key.group(),
+        PointConversionForm::UNCOMPRESSED,
+        &mut ctx,
+    )?;
+
+    let mut blob = SshWriter::default();
+    blob.string(SSH_CA_KEY_TYPE.as_bytes());
+    blob.string(SSH_CA_CURVE.as_bytes());
+    blob.string(&point);
+    Ok(blob.0)
+}
+
+/// The public key of an ssh certificate authority in the OpenSSH authorized keys format. This
+/// is the value that sshd must be configured to trust with `TrustedUserCAKeys`.
+pub fn ssh_ca_public_key(ca_key: &PKey<Private>) -> Result<String, CryptoError> {
+    let blob = ssh_ca_public_key_blob(ca_key)?;
+    Ok(format!("{} {}", SSH_CA_KEY_TYPE, STANDARD.encode(blob)))
+}
+
+/// Split an OpenSSH format public key into its type and the type specific fields of the key.
+fn parse_ssh_public_key(public_key: &str) -> Result<(&str, Vec<u8>), CryptoError> {
+    let mut parts = public_key.split_whitespace();
+    let (Some(key_type), Some(encoded)) = (parts.next(), parts.next()) else {
+        return Err(CryptoError::SshPublicKeyInvalid);
+    };
+
+    // Certificates can't be certified again.
+    if key_type.contains("-cert-") {
+        return Err(CryptoError::SshPublicKeyInvalid);
+    }
+
+    let blob = STANDARD
+        .decode(encoded)
+        .map_err(|_| CryptoError::SshPublicKeyInvalid)?;
+
+    let mut reader = SshReader(&blob);
+    if reader.string() != Some(key_type.as_bytes()) || reader.is_empty() {
+        return Err(CryptoError::SshPublicKeyInvalid);
+    }
+
+    Ok((key_type, reader.0.to_vec()))
+}
+
+fn ssh_ca_sign(ca_key: &PKey<Private>, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let ec_key = ca_key.ec_key()?;
+    let signature = EcdsaSig::sign(&sha256(data), &ec_key)?;
+
+    let mut inner = SshWriter::default();
+    inner.mpint(&signature.r().to_vec());
+    inner.mpint(&signature.s().to_vec());
+
+    let mut blob = SshWriter::default();
+    blob.string(SSH_CA_KEY_TYPE.as_bytes());
+    blob.string(&inner.0);
+    Ok(blob.0)
+}
+
+/// Issue a user certificate for an OpenSSH format public key. The certificate is valid for
+/// the given principals between `valid_after` and `valid_before`, which are seconds since the
+/// unix epoch. Returns the randomly assigned serial and the certificate in the OpenSSH format.
+pub fn build_ssh_user_certificate(
+    ca_key: &PKey<Private>,
+    public_key: &str,
+    key_id: &str,
+    principals: &[String],
+    valid_after: u64,
+    valid_before: u64,
+) -> Result<(u64, String), CryptoError> {
+    let (key_type, key_fields) = parse_ssh_public_key(public_key)?;
+
+    let cert_type = match key_type.strip_suffix("@openssh.com") {
+        Some(base) => format!("{base}-cert-v01@openssh.com"),
+        None => format!("{key_type}-cert-v01@openssh.com"),
+    };
+
+    let mut rng = rand::thread_rng();
+    let nonce: [u8; 32] = rng.gen();
+    let serial: u64 = rng.gen();
+
+    let mut valid_principals = SshWriter::default();
+    for principal in principals {
+        valid_principals.string(principal.as_bytes());
+    }
+
+    let mut extensions = SshWriter::default();
+    for extension in SSH_USER_CERTIFICATE_EXTENSIONS {
+        extensions.string(extension.as_bytes());
+        extensions.string(&[]);
+    }
+
+    let mut cert = SshWriter::default();
+    cert.string(cert_type.as_bytes());
+    cert.string(&nonce);
+    cert.raw(&key_fields);
+    cert.u64(serial);
+    cert.u32(SSH_CERT_TYPE_USER);
+    cert.string(key_id.as_bytes());
+    cert.string(&valid_principals.0);
+    cert.u64(valid_after);
+    cert.u64(valid_before);
+    // Critical options
+    cert.string(&[]);
+    cert.string(&extensions.0);
+    // Reserved
+    cert.string(&[]);
+    cert.string(&ssh_ca_public_key_blob(ca_key)?);
+
+    let signature = ssh_ca_sign(ca_key, &cert.0)?;
+    cert.string(&signature);
+
+    Ok((
+        serial,
+        format!("{} {} {}", cert_type, STANDARD.encode(&cert.0), key_id),
+    ))
+}
+
+/// Check if an OpenSSH format certificate was signed by the certificate authority `ca_key`.
+pub fn is_ssh_certificate_issued_by(certificate: &str, ca_key: &PKey<Private>) -> bool {
+    let Some(blob) = certificate
+        .split_whitespace()
+        .nth(1)
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+    else {
+        return false;
+    };
+
+    let Ok(ca_blob) = ssh_ca_public_key_blob(ca_key) else {
+        return false;
+    };
+
+    let mut signature_key = SshWriter::default();
+    signature_key.string(&ca_blob);
+    let signature_key = signature_key.0;
+
+    // The signature key of the authority is followed by the signature, which is the last field
+    // of the certificate. Everything before the signature is signed.
+    let Some(signed_len) = (0..blob.len())
+        .rev()
+        .filter(|start| blob[*start..].starts_with(&signature_key))
+        .map(|start| start + signature_key.len())
+        .find(|end| {
+            let mut reader = SshReader(&blob[*end..]);
+            reader.string().is_some() && reader.is_empty()
+        })
+    else {
+        return false;
+    };
+
+    let mut reader = SshReader(&blob[signed_len..]);
+    let Some(signature) = reader.string() else {
+        return false;
+    };
+
+    let mut reader = SshReader(signature);
+    if reader.string() != Some(SSH_CA_KEY_TYPE.as_bytes()) {
+        return false;
+    }
+
+    let Some(mut reader) = reader.string().map(SshReader) else {
+        return false;
+    };
+
+    let (Some(r), Some(s)) = (reader.string(), reader.string()) else {
+        return false;
+    };
+
+    BigNum::from_slice(r)
+        .and_then(|r| BigNum::from_slice(s).map(|s| (r, s)))
+        .and_then(|(r, s)| EcdsaSig::from_private_components(r, s))
+        .and_then(|signature| {
+            ca_key
+                .ec_key()
+                .and_then(|ec_key| signature.verify(&sha256(&blob[..signed_len]), &ec_key))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_ssh_user_certificate, is_ssh_certificate_issued_by, ssh_ca_public_key};
+    use crate::mtls::get_group;
+    use crate::CryptoError;
+    use openssl::ec::EcKey;
+    use openssl::pkey::PKey;
+
+    #[test]
+    fn test_ssh_user_certificate() {
+        let new_ca = || {
+            get_group()
+                .and_then(|group| EcKey::generate(&group))
+                .and_then(PKey::from_ec_key)
+                .expect("Unable to generate ca key")
+        };
+
+        let ca_key = new_ca();
+        let other_ca_key = new_ca();
+
+        let ca_public_key = ssh_ca_public_key(&ca_key).expect("Unable to encode ca key");
+        assert!(ca_public_key.starts_with("ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTY"));
+
+        let public_key =
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAeGW1P6Pc2rPq0XqbRaDKBcXZUPRklo0L1EyR30CwoP laptop";
+        let principals = vec!["testperson".to_string(), "admins".to_string()];
+
+        let (_serial, certificate) =
+            build_ssh_user_certificate(&ca_key, public_key, "testperson", &principals, 10, 20)
+                .expect("Unable to issue certificate");
+
+        assert!(certificate.starts_with("ssh-ed25519-cert-v01@openssh.com "));
+        assert!(is_ssh_certificate_issued_by(&certificate, &ca_key));
+        assert!(!is_ssh_certificate_issued_by(&certificate, &other_ca_key));
+
+        // A certificate can be issued for the key of the authority itself.
+        let (_serial, certificate) =
+            build_ssh_user_certificate(&ca_key, &ca_public_key, "ca", &principals, 10, 20)
+                .expect("Unable to issue certificate");
+        assert!(certificate.starts_with("ecdsa-sha2-nistp256-cert-v01@openssh.com "));
+        assert!(is_ssh_certificate_issued_by(&certificate, &ca_key));
+
+        // Mismatched key types, garbage and certificates are rejected.
+        for invalid in [
+            "ssh-rsa AAAAC3NzaC1lZDI1NTE5AAAAIAeGW1P6Pc2rPq0XqbRaDKBcXZUPRklo0L1EyR30CwoP",
+            "ssh-ed25519 invalid",
+            "ssh-ed25519",
+            certificate.as_str(),
+        ] {
+            assert!(matches!(
+                build_ssh_user_certificate(&ca_key, invalid, "testperson", &principals, 10, 20),
+                Err(CryptoError::SshPublicKeyInvalid)
+            ));
+        }
+    }
+}
@@ -0,0 +1,636 @@
+//! An implementation of yescrypt, so that password hashes imported from other systems (such as
+//! the shadow file of most current linux distributions) can be verified and then upgraded. This
+//! is only used to verify existing hashes, and follows the reference implementation.
+
+use crate::CryptoError;
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkcs5::pbkdf2_hmac;
+use openssl::pkey::PKey;
+use openssl::sha::sha256;
+use openssl::sign::Signer;
+
+const YESCRYPT_HASH64: &[u8] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const YESCRYPT_HASH_LEN: usize = 32;
+const YESCRYPT_MAX_SALT_LEN: usize = 64;
+
+const YESCRYPT_WORM: u32 = 0x001;
+const YESCRYPT_RW: u32 = 0x002;
+const YESCRYPT_RW_FLAVOR_MASK: u32 = 0x3fc;
+/// RW with 6 rounds, gather 4, simple 2 and 12k sboxes. This is the only read-write flavour
+/// that yescrypt defines the pwxform parameters for.
+const YESCRYPT_RW_DEFAULTS: u32 = 0x0b6;
+/// Internal flag of the first pass over a password that is hashed with a large N.
+const YESCRYPT_PREHASH: u32 = 0x1000_0000;
+
+/// An imported hash is verified on every authentication attempt, and the memory required is
+/// 128 * r * N bytes. This allows up to 64MiB, four times the default of libxcrypt, so that a
+/// hash can't be used to exhaust the server.
+const YESCRYPT_MAX_WORK: u64 = 1 << 19;
+
+const PWX_SIMPLE: usize = 2;
+const PWX_GATHER: usize = 4;
+const PWX_ROUNDS: usize = 6;
+const S_WIDTH: usize = 8;
+
+const PWX_WORDS: usize = PWX_GATHER * PWX_SIMPLE * 2;
+const S_PAIRS: usize = (1 << S_WIDTH) * PWX_SIMPLE;
+const S_WORDS: usize = 3 * S_PAIRS * 2;
+const S_MASK: usize = ((1 << S_WIDTH) - 1) * PWX_SIMPLE * 8;
+
+fn atoi64(c: u8) -> Option<u32> {
+    YESCRYPT_HASH64
+        .iter()
+        .position(|a| *a == c)
+        .map(|v| v as u32)
+}
+
+/// Decode a variable length integer of the parameter string.
+fn decode64_uint32(src: &mut &[u8], min: u32) -> Option<u32> {
+    let (c, rest) = src.split_first()?;
+    *src = rest;
+    let c = atoi64(*c)?;
+
+    let (mut start, mut end, mut chars, mut bits) = (0u32, 47u32, 1u32, 0u32);
+    let mut dst = min;
+    while c > end {
+        dst = dst.wrapping_add((end + 1 - start) << bits);
+        start = end + 1;
+        end = start + (62 - end) / 2;
+        chars += 1;
+        bits += 6;
+    }
+    dst = dst.wrapping_add((c - start) << bits);
+
+    for _ in 1..chars {
+        let (c, rest) = src.split_first()?;
+        *src = rest;
+        bits -= 6;
+        dst = dst.wrapping_add(atoi64(*c)? << bits);
+    }
+
+    Some(dst)
+}
+
+/// Decode bytes that are stored as little endian groups of 6 bits.
+fn decode64(src: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(src.len() * 3 / 4);
+
+    for chunk in src.chunks(4) {
+        if chunk.len() < 2 {
+            return None;
+        }
+        let mut value = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            value |= atoi64(*c)? << (i * 6);
+        }
+        let bytes = chunk.len() * 6 / 8;
+        out.extend_from_slice(&value.to_le_bytes()[..bytes]);
+        // The bits that don't form a full byte must be zero.
+        if value >> (bytes * 8) != 0 {
+            return None;
+        }
+    }
+
+    Some(out)
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Result<[u8; 32], CryptoError> {
+    let pkey = PKey::hmac(key)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
+    signer.update(data)?;
+    let mut out = [0u8; 32];
+    signer.sign(&mut out)?;
+    Ok(out)
+}
+
+fn blkxor(dst: &mut [u32], src: &[u32]) {
+    dst.iter_mut().zip(src).for_each(|(d, s)| *d ^= s);
+}
+
+/// Apply the salsa20 core to a block that is in the shuffled order of the reference
+/// implementation.
+fn salsa20(b: &mut [u32], rounds: usize) {
+    let mut x = [0u32; 16];
+    for i in 0..16 {
+        x[i * 5 % 16] = b[i];
+    }
+
+    fn quarter(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    }
+
+    for _ in (0..rounds).step_by(2) {
+        // Columns
+        quarter(&mut x, 0, 4, 8, 12);
+        quarter(&mut x, 5, 9, 13, 1);
+        quarter(&mut x, 10, 14, 2, 6);
+        quarter(&mut x, 15, 3, 7, 11);
+        // Rows
+        quarter(&mut x, 0, 1, 2, 3);
+        quarter(&mut x, 5, 6, 7, 4);
+        quarter(&mut x, 10, 11, 8, 9);
+        quarter(&mut x, 15, 12, 13, 14);
+    }
+
+    for i in 0..16 {
+        b[i] = b[i].wrapping_add(x[i * 5 % 16]);
+    }
+}
+
+fn blockmix_salsa8(b: &mut [u32], y: &mut [u32], r: usize) {
+    let mut x = [0u32; 16];
+    x.copy_from_slice(&b[(2 * r - 1) * 16..]);
+
+    for i in 0..2 * r {
+        blkxor(&mut x, &b[i * 16..(i + 1) * 16]);
+        salsa20(&mut x, 8);
+        y[i * 16..(i + 1) * 16].copy_from_slice(&x);
+    }
+
+    for i in 0..r {
+        b[i * 16..(i + 1) * 16].copy_from_slice(&y[(i * 2) * 16..(i * 2 + 1) * 16]);
+        b[(i + r) * 16..(i + r + 1) * 16].copy_from_slice(&y[(i * 2 + 1) * 16..(i * 2 + 2) * 16]);
+    }
+}
+
+/// The sboxes and write position of pwxform. The offsets are in words of `s`.
+struct Pwxform {
+    s: Vec<u32>,
+    s0: usize,
+    s1: usize,
+    s2: usize,
+    w: usize,
+}
+
+impl Pwxform {
+    fn new(s: Vec<u32>) -> Self {
+        Pwxform {
+            s,
+            s0: S_PAIRS * 4,
+            s1: S_PAIRS * 2,
+            s2: 0,
+            w: 0,
+        }
+    }
+
+    fn transform(&mut self, b: &mut [u32]) {
+        for i in 0..PWX_ROUNDS {
+            for j in 0..PWX_GATHER {
+                let xl = b[j * PWX_SIMPLE * 2] as usize;
+                let xh = b[j * PWX_SIMPLE * 2 + 1] as usize;
+                let p0 = self.s0 + (xl & S_MASK) / 4;
+                let p1 = self.s1 + (xh & S_MASK) / 4;
+
+                for k in 0..PWX_SIMPLE {
+                    let idx = (j * PWX_SIMPLE + k) * 2;
+                    let s0 = ((self.s[p0 + k * 2 + 1] as u64) << 32) | self.s[p0 + k * 2] as u64;
+                    let s1 = ((self.s[p1 + k * 2 + 1] as u64) << 32) | self.s[p1 + k * 2] as u64;
+
+                    let x = ((b[idx + 1] as u64) * (b[idx] as u64)).wrapping_add(s0) ^ s1;
+                    b[idx] = x as u32;
+                    b[idx + 1] = (x >> 32) as u32;
+
+                    if i != 0 && i != PWX_ROUNDS - 1 {
+                        self.s[self.s2 + self.w * 2] = x as u32;
+                        self.s[self.s2 + self.w * 2 + 1] = (x >> 32) as u32;
+                        self.w += 1;
+                    }
+                }
+            }
+        }
+
+        (self.s0, self.s1, self.s2) = (self.s2, self.s0, self.s1);
+        self.w &= S_PAIRS - 1;
+    }
+}
+
+fn blockmix_pwxform(b: &mut [u32], ctx: &mut Pwxform, r: usize) {
+    let r1 = 128 * r / (PWX_WORDS * 4);
+    let mut x = [0u32; PWX_WORDS];
+    x.copy_from_slice(&b[(r1 - 1) * PWX_WORDS..r1 * PWX_WORDS]);
+
+    for i in 0..r1 {
+        if r1 > 1 {
+            blkxor(&mut x, &b[i * PWX_WORDS..(i + 1) * PWX_WORDS]);
+        }
+        ctx.transform(&mut x);
+        b[i * PWX_WORDS..(i + 1) * PWX_WORDS].copy_from_slice(&x);
+    }
+
+    // With these pwxform parameters only the last block is hashed.
+    let i = (r1 - 1) * PWX_WORDS * 4 / 64;
+    salsa20(&mut b[i * 16..(i + 1) * 16], 2);
+    for i in i + 1..2 * r {
+        let (prev, cur) = b.split_at_mut(i * 16);
+        blkxor(&mut cur[..16], &prev[(i - 1) * 16..]);
+        salsa20(&mut cur[..16], 2);
+    }
+}
+
+fn blockmix(x: &mut [u32], y: &mut [u32], ctx: Option<&mut Pwxform>, r: usize) {
+    match ctx {
+        Some(ctx) => blockmix_pwxform(x, ctx, r),
+        None => blockmix_salsa8(x, y, r),
+    }
+}
+
+fn integerify(x: &[u32], r: usize) -> u64 {
+    let x = &x[(2 * r - 1) * 16..];
+    ((x[13] as u64) << 32) | x[0] as u64
+}
+
+/// The largest power of 2 that isn't greater than x.
+fn p2floor(x: u64) -> u64 {
+    match x {
+        0 => 0,
+        x => 1 << (63 - x.leading_zeros()),
+    }
+}
+
+fn wrap(x: u64, i: u64) -> u64 {
+    let n = p2floor(i);
+    (x & (n - 1)) + (i - n)
+}
+
+fn shuffle_in(b: &[u32], x: &mut [u32]) {
+    for (xk, bk) in x.chunks_exact_mut(16).zip(b.chunks_exact(16)) {
+        for i in 0..16 {
+            xk[i] = bk[i * 5 % 16];
+        }
+    }
+}
+
+fn shuffle_out(x: &[u32], b: &mut [u32]) {
+    for (xk, bk) in x.chunks_exact(16).zip(b.chunks_exact_mut(16)) {
+        for i in 0..16 {
+            bk[i * 5 % 16] = xk[i];
+        }
+    }
+}
+
+fn smix1(
+    b: &mut [u32],
+    r: usize,
+    n: u64,
+    flags: u32,
+    v: &mut [u32],
+    mut ctx: Option<&mut Pwxform>,
+) {
+    let s = 32 * r;
+    let mut x = vec![0u32; s];
+    let mut y = vec![0u32; s];
+    shuffle_in(b, &mut x);
+
+    for i in 0..n as usize {
+        v[i * s..(i + 1) * s].copy_from_slice(&x);
+        if flags & YESCRYPT_RW != 0 && i > 1 {
+            let j = wrap(integerify(&x, r), i as u64) as usize;
+            blkxor(&mut x, &v[j * s..(j + 1) * s]);
+        }
+        blockmix(&mut x, &mut y, ctx.as_deref_mut(), r);
+    }
+
+    shuffle_out(&x, b);
+}
+
+fn smix2(
+    b: &mut [u32],
+    r: usize,
+    n: u64,
+    nloop: u64,
+    flags: u32,
+    v: &mut [u32],
+    mut ctx: Option<&mut Pwxform>,
+) {
+    if nloop == 0 {
+        return;
+    }
+
+    let s = 32 * r;
+    let mut x = vec![0u32; s];
+    let mut y = vec![0u32; s];
+    shuffle_in(b, &mut x);
+
+    for _ in 0..nloop {
+        let j = (integerify(&x, r) & (n - 1)) as usize;
+        blkxor(&mut x, &v[j * s..(j + 1) * s]);
+        if flags & YESCRYPT_RW != 0 {
+            v[j * s..(j + 1) * s].copy_from_slice(&x);
+        }
+        blockmix(&mut x, &mut y, ctx.as_deref_mut(), r);
+    }
+
+    shuffle_out(&x, b);
+}
+
+#[derive(Clone, Copy)]
+struct Params {
+    flags: u32,
+    n: u64,
+    r: usize,
+    p: usize,
+    t: u32,
+}
+
+fn smix(
+    b: &mut [u32],
+    params: Params,
+    v: &mut [u32],
+    passwd: &mut [u8; 32],
+) -> Result<(), CryptoError> {
+    let Params { flags, n, r, p, t } = params;
+    let s = 32 * r;
+    let p_u64 = p as u64;
+
+    let mut nchunk = n / p_u64;
+    let mut nloop_all = nchunk;
+    if flags & YESCRYPT_RW != 0 {
+        if t <= 1 {
+            if t == 1 {
+                nloop_all *= 2;
+            }
+            nloop_all = nloop_all.div_ceil(3);
+        } else {
+            nloop_all *= (t - 1) as u64;
+        }
+    } else if t != 0 {
+        if t == 1 {
+            nloop_all += nloop_all.div_ceil(2);
+        }
+        nloop_all *= t as u64;
+    }
+
+    let mut nloop_rw = if flags & YESCRYPT_RW != 0 {
+        nloop_all / p_u64
+    } else {
+        0
+    };
+
+    nchunk &= !1;
+    nloop_all = (nloop_all + 1) & !1;
+    nloop_rw = (nloop_rw + 1) & !1;
+
+    let mut ctxs = Vec::with_capacity(p);
+    let mut vchunk = 0;
+    for i in 0..p {
+        let np = if i < p - 1 { nchunk } else { n - vchunk };
+        let bp = &mut b[s * i..s * (i + 1)];
+        let vp = &mut v[s * vchunk as usize..s * (vchunk + np) as usize];
+
+        let mut ctx = if flags & YESCRYPT_RW != 0 {
+            let mut sbox = vec![0u32; S_WORDS];
+            smix1(bp, 1, (S_WORDS * 4 / 128) as u64, 0, &mut sbox, None);
+            if i == 0 {
+                let key: Vec<u8> = bp[s - 16..].iter().flat_map(|w| w.to_le_bytes()).collect();
+                *passwd = hmac_sha256(&key, passwd)?;
+            }
+            Some(Pwxform::new(sbox))
+        } else {
+            None
+        };
+
+        smix1(bp, r, np, flags, vp, ctx.as_mut());
+        smix2(bp, r, p2floor(np), nloop_rw, flags, vp, ctx.as_mut());
+        ctxs.push(ctx);
+        vchunk += nchunk;
+    }
+
+    for (i, ctx) in ctxs.iter_mut().enumerate() {
+        smix2(
+            &mut b[s * i..s * (i + 1)],
+            r,
+            n,
+            nloop_all - nloop_rw,
+            flags & !YESCRYPT_RW,
+            v,
+            ctx.as_mut(),
+        );
+    }
+
+    Ok(())
+}
+
+fn kdf_body(passwd: &[u8], salt: &[u8], params: Params) -> Result<[u8; 32], CryptoError> {
+    let Params { flags, n, r, p, .. } = params;
+    let s = 32 * r;
+
+    let prehash;
+    let passwd = if flags != 0 {
+        let key: &[u8] = if flags & YESCRYPT_PREHASH != 0 {
+            b"yescrypt-prehash"
+        } else {
+            b"yescrypt"
+        };
+        prehash = hmac_sha256(key, passwd)?;
+        &prehash
+    } else {
+        passwd
+    };
+
+    let mut bytes = vec![0u8; 4 * s * p];
+    pbkdf2_hmac(passwd, salt, 1, MessageDigest::sha256(), &mut bytes)?;
+
+    // The password of the final pbkdf2 is derived from the start of B.
+    let mut sha = [0u8; 32];
+    sha.copy_from_slice(&bytes[..32]);
+
+    let mut b: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    let mut v = vec![0u32; s * n as usize];
+
+    if p == 1 || flags & YESCRYPT_RW != 0 {
+        smix(&mut b, params, &mut v, &mut sha)?;
+    } else {
+        for i in 0..p {
+            smix(
+                &mut b[s * i..s * (i + 1)],
+                Params { p: 1, ..params },
+                &mut v,
+                &mut sha,
+            )?;
+        }
+    }
+
+    let bytes: Vec<u8> = b.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let passwd = if flags != 0 { &sha[..] } else { passwd };
+    let mut out = [0u8; 32];
+    pbkdf2_hmac(passwd, &bytes, 1, MessageDigest::sha256(), &mut out)?;
+
+    if flags != 0 && flags & YESCRYPT_PREHASH == 0 {
+        // Stored key, as in SCRAM.
+        let client_key = hmac_sha256(&out, b"Client Key")?;
+        out = sha256(&client_key);
+    }
+
+    Ok(out)
+}
+
+/// A parsed yescrypt hash in the modular crypt format, `$y$<params>$<salt>$<hash>`.
+pub struct YescryptHash {
+    params: Params,
+    salt: Vec<u8>,
+    hash: Vec<u8>,
+}
+
+impl YescryptHash {
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.strip_prefix("$y$")?.split('$');
+        let (params, salt, hash) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let mut src = params.as_bytes();
+        let flavor = decode64_uint32(&mut src, 0)?;
+        let flags = if flavor < YESCRYPT_RW {
+            flavor
+        } else if flavor <= YESCRYPT_RW + (YESCRYPT_RW_FLAVOR_MASK >> 2) {
+            YESCRYPT_RW + ((flavor - YESCRYPT_RW) << 2)
+        } else {
+            return None;
+        };
+
+        let n_log2 = decode64_uint32(&mut src, 1)?;
+        let r = decode64_uint32(&mut src, 1)?;
+        let (mut p, mut t) = (1, 0);
+        if !src.is_empty() {
+            let have = decode64_uint32(&mut src, 1)?;
+            // Hash upgrades and ROMs are not supported.
+            if have & !0x3 != 0 {
+                return None;
+            }
+            if have & 0x1 != 0 {
+                p = decode64_uint32(&mut src, 2)?;
+            }
+            if have & 0x2 != 0 {
+                t = decode64_uint32(&mut src, 1)?;
+            }
+        }
+        if !src.is_empty() {
+            return None;
+        }
+
+        let valid_flags = match flags {
+            0 => t == 0,
+            YESCRYPT_WORM => true,
+            YESCRYPT_RW_DEFAULTS => true,
+            _ => false,
+        };
+        if !valid_flags || !(1..=32).contains(&n_log2) {
+            return None;
+        }
+
+        let n = 1u64 << n_log2;
+        let work = n
+            .checked_mul(r as u64)?
+            .checked_mul(p as u64)?
+            .checked_mul(t as u64 + 1)?;
+        if work > YESCRYPT_MAX_WORK || (flags & YESCRYPT_RW != 0 && n / p as u64 <= 1) {
+            return None;
+        }
+
+        let salt = decode64(salt.as_bytes()).filter(|s| s.len() <= YESCRYPT_MAX_SALT_LEN)?;
+        let hash = decode64(hash.as_bytes()).filter(|h| h.len() == YESCRYPT_HASH_LEN)?;
+
+        Some(YescryptHash {
+            params: Params {
+                flags,
+                n,
+                r: r as usize,
+                p: p as usize,
+                t,
+            },
+            salt,
+            hash,
+        })
+    }
+
+    pub fn verify(&self, pass: &[u8]) -> Result<bool, CryptoError> {
+        let Params { flags, n, r, p, .. } = self.params;
+
+        // As in the reference implementation, when N is large the password is first hashed
+        // with N / 64.
+        let prehash;
+        let pass = if flags & YESCRYPT_RW != 0
+            && n / p as u64 >= 0x100
+            && n / p as u64 * r as u64 >= 0x20000
+        {
+            prehash = kdf_body(
+                pass,
+                &self.salt,
+                Params {
+                    flags: flags | YESCRYPT_PREHASH,
+                    n: n >> 6,
+                    t: 0,
+                    ..self.params
+                },
+            )?;
+            &prehash[..]
+        } else {
+            pass
+        };
+
+        let hash = kdf_body(pass, &self.salt, self.params)?;
+        Ok(memcmp::eq(&hash, &self.hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yescrypt_verify() {
+        // Created with the crypt(3) of libxcrypt.
+        for hash in [
+            // The default parameters of libxcrypt, which are hashed twice
+            "$y$j9T$F5Jx5fExrKuPp53xLKQ..1$tnSYvahCwPBHKZUspmcxMfb0.WiB9W.zEaKlOBL35rC",
+            "$y$j75$saltsaltsaltsalt$hI02SdBpr3mSssvBRd05Dwe0nTFc/hsy01KTxh646J.",
+            // p = 2, t = 1
+            "$y$j750/.$saltsaltsaltsalt$947V93zQ.jHBUZ2X0krLFAJFV6pe/azDN5bJFrLMVy1",
+            // t = 2
+            "$y$j5T./$saltsaltsaltsalt$.12FSxV96A3bzzCsdSFbEWEJLv6tJIaOPSnJaceEF.8",
+            // WORM
+            "$y$/7T$saltsaltsaltsalt$.riTtNsMjzQ.Sd80aU4gm/zkvFJM.5Y9VTgoIeb18xC",
+            "$y$/750..$saltsaltsaltsalt$X9NdKteK8AdGPoIVz88UTBwsK2HoIRGfkT5M4T5jTi8",
+            // Classic scrypt
+            "$y$.7/$saltsaltsaltsalt$xXtDDrnaS56PEmgZZcPtg4oZgaaVcCIuKuJ/LvEH.K.",
+        ] {
+            let h = YescryptHash::parse(hash).expect("Invalid hash");
+            assert!(h.verify(b"password").expect("Failed to verify"));
+            assert!(!h.verify(b"password1").expect("Failed to verify"));
+        }
+    }
+
+    #[test]
+    fn test_yescrypt_parse_invalid() {
+        // N is too large
+        assert!(YescryptHash::parse(
+            "$y$jDT$saltsaltsaltsalt$hI02SdBpr3mSssvBRd05Dwe0nTFc/hsy01KTxh646J."
+        )
+        .is_none());
+        // Hash upgrades are not supported
+        assert!(YescryptHash::parse(
+            "$y$j751.$saltsaltsaltsalt$hI02SdBpr3mSssvBRd05Dwe0nTFc/hsy01KTxh646J."
+        )
+        .is_none());
+        // Unknown read-write flavour
+        assert!(YescryptHash::parse(
+            "$y$i75$saltsaltsaltsalt$hI02SdBpr3mSssvBRd05Dwe0nTFc/hsy01KTxh646J."
+        )
+        .is_none());
+        // Truncated
+        assert!(YescryptHash::parse("$y$j75$saltsaltsaltsalt$hI02SdBpr3mSssvBRd05Dwe0").is_none());
+        // Invalid characters
+        assert!(YescryptHash::parse(
+            "$y$j75$saltsaltsaltsalt$hI02SdBpr3mSssvBRd05Dwe0nTFc+hsy01KTxh646J."
+        )
+        .is_none());
+    }
+}
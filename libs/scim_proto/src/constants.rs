@@ -9,6 +9,11 @@ pub const SCIM_SCHEMA_PREIX: &str = "urn:ietf:params:scim:api:";
 pub const SCIM_SCHEMA_USER: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
 pub const SCIM_SCHEMA_GROUP: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
 
+// https://datatracker.ietf.org/doc/html/rfc7644#section-3.4.2
+pub const SCIM_SCHEMA_LIST_RESPONSE: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+// https://datatracker.ietf.org/doc/html/rfc7644#section-3.5.2
+pub const SCIM_SCHEMA_PATCH_OP: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+
 #[cfg(test)]
 pub(crate) const RFC7643_USER: &str = r#"
 {
@@ -5,7 +5,7 @@ use std::fmt::Display;
 use std::str::FromStr;
 
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tracing_forest::printer::TestCapturePrinter;
 use tracing_forest::tag::NoTag;
 use tracing_forest::util::*;
@@ -105,7 +105,7 @@ impl EventTag {
     }
 }
 
-#[derive(Clone, Copy, Deserialize, Debug, Default)]
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, Default, PartialEq, Eq)]
 pub enum LogLevel {
     #[default]
     #[serde(rename = "info")]
@@ -2,16 +2,26 @@ use std::{str::FromStr, time::Duration};
 
 use opentelemetry_otlp::{Protocol, WithExportConfig};
 
-use opentelemetry::{global, trace::TracerProvider as _, KeyValue};
+use opentelemetry::{
+    global,
+    propagation::{Extractor, TextMapPropagator},
+    trace::{TraceContextExt, TracerProvider as _},
+    KeyValue,
+};
 
 use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
     trace::{Sampler, TracerProvider},
     Resource,
 };
-use tracing::Subscriber;
+use tracing::{Span, Subscriber};
 use tracing_core::Level;
 
-use tracing_subscriber::{filter::Directive, prelude::*, EnvFilter, Registry};
+use tracing_subscriber::{
+    filter::{Directive, LevelFilter},
+    prelude::*,
+    reload, EnvFilter, Registry,
+};
 
 pub const MAX_EVENTS_PER_SPAN: u32 = 64 * 1024;
 pub const MAX_ATTRIBUTES_PER_SPAN: u32 = 128;
@@ -38,14 +48,48 @@ use opentelemetry_semantic_conventions::{
 //         .build()
 // }
 
+/// Changes the log level of a running logging pipeline, such as when the configuration
+/// of a server is reloaded.
+pub struct LogLevelReloadHandle(reload::Handle<EnvFilter, Registry>);
+
+impl LogLevelReloadHandle {
+    pub fn set(&self, log_filter: crate::LogLevel) -> Result<(), String> {
+        self.0
+            .reload(log_level_filter(log_filter))
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// The filter for a log level. Directives in `RUST_LOG` take precedence over the level.
+fn log_level_filter(log_filter: crate::LogLevel) -> EnvFilter {
+    EnvFilter::builder()
+        .with_default_directive(log_filter.into())
+        .from_env_lossy()
+}
+
 /// This does all the startup things for the logging pipeline
 pub fn start_logging_pipeline(
     otlp_endpoint: &Option<String>,
     log_filter: crate::LogLevel,
     service_name: &'static str,
 ) -> Result<Box<dyn Subscriber + Send + Sync>, String> {
+    start_reloadable_logging_pipeline(otlp_endpoint, log_filter, service_name)
+        .map(|(subscriber, _)| subscriber)
+}
+
+/// Start the logging pipeline, with a handle that allows the log level to be changed while
+/// it is running.
+pub fn start_reloadable_logging_pipeline(
+    otlp_endpoint: &Option<String>,
+    log_filter: crate::LogLevel,
+    service_name: &'static str,
+) -> Result<(Box<dyn Subscriber + Send + Sync>, LogLevelReloadHandle), String> {
+    // The level applies to all layers, so that it can be reloaded in one place.
+    let (level_filter, level_handle) = reload::Layer::new(log_level_filter(log_filter));
+    let level_handle = LogLevelReloadHandle(level_handle);
+
     let forest_filter: EnvFilter = EnvFilter::builder()
-        .with_default_directive(log_filter.into())
+        .with_default_directive(LevelFilter::TRACE.into())
         .from_env_lossy();
 
     // TODO: work out how to do metrics things
@@ -64,7 +108,7 @@ pub fn start_logging_pipeline(
                 );
             let forest_layer = tracing_forest::ForestLayer::default().with_filter(forest_filter);
             let t_filter: EnvFilter = EnvFilter::builder()
-                .with_default_directive(log_filter.into())
+                .with_default_directive(LevelFilter::TRACE.into())
                 .from_env_lossy();
 
             let otlp_exporter = opentelemetry_otlp::SpanExporter::builder()
@@ -107,10 +151,12 @@ pub fn start_logging_pipeline(
                 .build();
 
             global::set_tracer_provider(provider.clone());
+            global::set_text_map_propagator(TraceContextPropagator::new());
             provider.tracer("tracing-otel-subscriber");
             use tracing_opentelemetry::OpenTelemetryLayer;
 
             let registry = tracing_subscriber::registry()
+                .with(level_filter)
                 .with(
                     tracing_subscriber::filter::LevelFilter::from_level(Level::INFO)
                         .with_filter(t_filter),
@@ -122,13 +168,58 @@ pub fn start_logging_pipeline(
                     provider.tracer("tracing-otel-subscriber"),
                 ));
 
-            Ok(Box::new(registry))
+            Ok((Box::new(registry), level_handle))
         }
         None => {
             let forest_layer = tracing_forest::ForestLayer::default().with_filter(forest_filter);
-            Ok(Box::new(Registry::default().with(forest_layer)))
+            Ok((
+                Box::new(Registry::default().with(level_filter).with(forest_layer)),
+                level_handle,
+            ))
+        }
+    }
+}
+
+/// The W3C trace context headers of an incoming request.
+pub struct TraceContextHeaders<'a> {
+    pub traceparent: Option<&'a str>,
+    pub tracestate: Option<&'a str>,
+}
+
+impl Extractor for TraceContextHeaders<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        match key {
+            "traceparent" => self.traceparent,
+            "tracestate" => self.tracestate,
+            _ => None,
         }
     }
+
+    fn keys(&self) -> Vec<&str> {
+        vec!["traceparent", "tracestate"]
+    }
+}
+
+/// Continue the trace of a caller that sent W3C trace context headers, so that our spans
+/// are joined to theirs. If the headers are absent or invalid, `span` remains a new trace.
+pub fn continue_trace(span: &Span, headers: &TraceContextHeaders<'_>) {
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let parent_cx = TraceContextPropagator::new().extract(headers);
+    if parent_cx.span().span_context().is_valid() {
+        span.set_parent(parent_cx);
+    }
+}
+
+/// The OpenTelemetry trace id of `span`. This is `None` when traces are not being exported.
+pub fn trace_id(span: &Span) -> Option<[u8; 16]> {
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let cx = span.context();
+    let span_context = cx.span().span_context().clone();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_bytes())
 }
 
 /// This helps with cleanly shutting down the tracing/logging providers when done,
@@ -141,3 +232,73 @@ impl Drop for TracingPipelineGuard {
         eprintln!("Logging pipeline completed shutdown");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use tracing::Span;
+    use tracing_subscriber::prelude::*;
+
+    use super::{continue_trace, trace_id, TraceContextHeaders};
+
+    const TRACE_ID: u128 = 0x4bf9_2f35_77b3_4da6_a3ce_929d_0e0e_4736;
+
+    /// Create a request span, with the trace id it has once the headers are applied. Spans
+    /// are exported to a tracer, as they are when an otel endpoint is configured.
+    fn request_trace_id(traceparent: Option<&str>) -> Option<[u8; 16]> {
+        let provider = TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request");
+            continue_trace(
+                &span,
+                &TraceContextHeaders {
+                    traceparent,
+                    tracestate: None,
+                },
+            );
+            trace_id(&span)
+        })
+    }
+
+    #[test]
+    fn test_continue_trace() {
+        let traceparent = format!("00-{TRACE_ID:032x}-00f067aa0ba902b7-01");
+        assert_eq!(
+            request_trace_id(Some(&traceparent)),
+            Some(TRACE_ID.to_be_bytes())
+        );
+    }
+
+    #[test]
+    fn test_continue_trace_new_trace() {
+        // Without a valid header, each request starts a trace of its own.
+        let first = request_trace_id(None).expect("No trace id was created");
+        let second = request_trace_id(None).expect("No trace id was created");
+        assert_ne!(first, second);
+
+        for traceparent in [
+            format!("00-{TRACE_ID:032x}"),
+            format!("00-{TRACE_ID:031x}z-00f067aa0ba902b7-01"),
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01".to_string(),
+            format!("00-{TRACE_ID:032x}-0000000000000000-01"),
+        ] {
+            let created = request_trace_id(Some(&traceparent)).expect("No trace id was created");
+            assert_ne!(created, TRACE_ID.to_be_bytes());
+            assert_ne!(created, [0; 16]);
+        }
+    }
+
+    #[test]
+    fn test_trace_id_not_exported() {
+        // There is no trace id when spans are only logged.
+        let subscriber = tracing_subscriber::registry();
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(trace_id(&tracing::info_span!("request")), None);
+            assert_eq!(trace_id(&Span::none()), None);
+        });
+    }
+}
@@ -16,7 +16,9 @@ pub use smartstring::alias::String as AttrString;
 #[serde(rename_all = "lowercase", try_from = "&str", into = "AttrString")]
 pub enum Attribute {
     Account,
+    AccountArchiveDelay,
     AccountExpire,
+    AccountLifetime,
     AccountValidFrom,
     AcpCreateAttr,
     AcpCreateClass,
@@ -27,7 +29,14 @@ pub enum Attribute {
     AcpReceiver,
     AcpReceiverGroup,
     AcpSearchAttr,
+    AcpStaged,
+    AcpTargetGroup,
     AcpTargetScope,
+    AnnouncementCli,
+    AnnouncementEnd,
+    AnnouncementMessage,
+    AnnouncementSeverity,
+    AnnouncementStart,
     ApiTokenSession,
     ApplicationPassword,
     AttestedPasskeys,
@@ -35,7 +44,14 @@ pub enum Attribute {
     Attr,
     AttributeName,
     AttributeType,
+    AuthLockoutDuration,
+    AuthLockoutThreshold,
+    AuthLockoutWindow,
     AuthSessionExpiry,
+    AuthSessionIdleTimeout,
+    AuthTrustedNetwork,
+    AuthPasswordHistoryCount,
+    AuthPasswordMaximumAge,
     AuthPasswordMinimumLength,
     BadlistPassword,
     Certificate,
@@ -46,8 +62,11 @@ pub enum Attribute {
     CookiePrivateKey,
     CreatedAtCid,
     CredentialUpdateIntentToken,
+    CredentialUsage,
     CredentialTypeMinimum,
+    CredentialTypeMinimumUntrustedNetwork,
     DeniedName,
+    DeniedPasswordTerm,
     Description,
     DirectMemberOf,
     DisplayName,
@@ -57,8 +76,11 @@ pub enum Attribute {
     DomainDevelopmentTaint,
     DomainDisplayName,
     DomainLdapBasedn,
+    DomainLoginBanner,
     DomainName,
+    DomainPrimaryColour,
     DomainSsid,
+    DomainTheme,
     DomainTokenKey,
     DomainUuid,
     DynGroup,
@@ -77,12 +99,26 @@ pub enum Attribute {
     GidNumber,
     GrantUiHint,
     Group,
+    HbacDeny,
+    HbacHost,
+    HbacHostGroup,
+    HbacMember,
+    HbacService,
+    HostGroupMember,
+    HostJoinToken,
+    HostJoinTokenExpiry,
+    HostKioskCacheTimeout,
+    HostKioskGuestGroup,
+    HostKioskMode,
+    HostTag,
     IdVerificationEcKey,
     Image,
     Index,
     IpaNtHash,
     IpaSshPubKey,
     JwsEs256PrivateKey,
+    JwtPublicKey,
+    KerberosServicePrincipal,
     KeyActionRotate,
     KeyActionRevoke,
     KeyActionImportJwsEs256,
@@ -90,6 +126,7 @@ pub enum Attribute {
     KeyProvider,
     LastModifiedCid,
     LdapAllowUnixPwBind,
+    LdapGroupCompat,
     /// An LDAP Compatible emailAddress
     LdapEmailAddress,
     /// An LDAP Compatible sshkeys virtual attribute
@@ -99,40 +136,85 @@ pub enum Attribute {
     LimitSearchMaxResults,
     LimitSearchMaxFilterTest,
     LinkedGroup,
+    LoginHostTag,
     LoginShell,
     Mail,
     May,
     Member,
     MemberOf,
+    MemberValidUntil,
+    MembershipRequestable,
+    MembershipRequestDecidedBy,
+    MembershipRequestGroup,
+    MembershipRequester,
+    MembershipRequestStatus,
+    MembershipRequestValidUntil,
+    MigrationAppliedAt,
+    MigrationDuration,
+    MigrationEntriesChanged,
+    MigrationFromLevel,
+    MigrationName,
+    MigrationToLevel,
     MultiValue,
     Must,
     Name,
     NameHistory,
     NoIndex,
+    NotificationOptOut,
     NsUniqueId,
     NsAccountLock,
     OAuth2AllowInsecureClientDisablePkce,
+    OAuth2AllowInsecureRefreshTokenReuse,
     OAuth2AllowLocalhostRedirect,
+    OAuth2AllowedGrantTypes,
+    OAuth2AllowedResponseTypes,
     OAuth2ConsentScopeMap,
     OAuth2DeviceFlowEnable,
     OAuth2JwtLegacyCryptoEnable,
+    OAuth2JwtSignAlg,
     OAuth2PreferShortUsername,
+    OAuth2RedirectUriMode,
+    OAuth2RsAssignedMember,
     OAuth2RsBasicSecret,
+    OAuth2RsBasicSecretExpiry,
+    OAuth2RsBasicSecretLastUsed,
+    OAuth2RsBasicSecretPrevious,
+    OAuth2RsBasicSecretPreviousExpiry,
+    OAuth2RsBasicSecretPreviousLastUsed,
     OAuth2RsClaimMap,
     OAuth2RsImplicitScopes,
     OAuth2RsName,
     OAuth2RsOrigin,
     OAuth2RsOriginLanding,
+    OAuth2RsScopeDescription,
+    OAuth2RsComputedClaim,
     OAuth2RsScopeMap,
     OAuth2RsSupScopeMap,
     OAuth2RsTokenKey,
     OAuth2Session,
     OAuth2StrictRedirectUri,
+    OAuth2TokenExchangeAudience,
     ObjectClass,
+    OidcUpstreamAuthorisationEndpoint,
+    OidcUpstreamClientId,
+    OidcUpstreamClientSecret,
+    OidcUpstreamIssuer,
+    OidcUpstreamJwksUri,
+    OidcUpstreamTokenEndpoint,
     OtherNoIndex,
+    ParentGroup,
     PassKeys,
+    PasswordBreachCheck,
+    PasswordHistory,
+    PasswordLastChanged,
     PasswordImport,
     PatchLevel,
+    Phone,
+    PosixDefaultShell,
+    PosixGecosFormat,
+    PosixHomeQuota,
+    PosixHomeSkeleton,
+    PosixHomeTemplate,
     Phantom,
     PrimaryCredential,
     PrivateCookieKey,
@@ -142,16 +224,23 @@ pub enum Attribute {
     Refers,
     Replicated,
     Rs256PrivateKeyDer,
+    Saml2AcsUrl,
+    Saml2EntityId,
+    Saml2GroupMap,
     /// A set of scim schemas. This is similar to a kanidm class.
     #[serde(rename = "schemas")]
     ScimSchemas,
     Scope,
     SourceUuid,
     Spn,
+    Sponsor,
     /// An LDAP-compatible sshpublickey
     LdapSshPublicKey,
     /// The Kanidm-local ssh_publickey
     SshPublicKey,
+    SshPublicKeyExpiry,
+    SshKeyAllowedType,
+    SshKeyRsaMinimumBits,
     SudoHost,
     Supplements,
     SystemSupplements,
@@ -163,12 +252,15 @@ pub enum Attribute {
     SyncParentUuid,
     SyncTokenSession,
     SyncYieldAuthority,
+    SyncConflict,
     Syntax,
     SystemExcludes,
     SystemMay,
     SystemMust,
+    TelephoneNumber,
     Term,
     TotpImport,
+    UiTheme,
     Uid,
     UidNumber,
     Unique,
@@ -180,7 +272,15 @@ pub enum Attribute {
     Uuid,
     Version,
     WebauthnAttestationCaList,
+    WebhookFilter,
+    WebhookSecret,
+    WebhookUrl,
+    WhitePagesRateLimit,
+    WhitePagesTrustedNetwork,
+    AllowApiTokens,
+    AllowEmailLink,
     AllowPrimaryCredFallback,
+    AllowUnixPassword,
 
     #[cfg(any(debug_assertions, test, feature = "test"))]
     NonExist,
@@ -248,7 +348,9 @@ impl Attribute {
     pub fn as_str(&self) -> &str {
         match self {
             Attribute::Account => ATTR_ACCOUNT,
+            Attribute::AccountArchiveDelay => ATTR_ACCOUNT_ARCHIVE_DELAY,
             Attribute::AccountExpire => ATTR_ACCOUNT_EXPIRE,
+            Attribute::AccountLifetime => ATTR_ACCOUNT_LIFETIME,
             Attribute::AccountValidFrom => ATTR_ACCOUNT_VALID_FROM,
             Attribute::AcpCreateAttr => ATTR_ACP_CREATE_ATTR,
             Attribute::AcpCreateClass => ATTR_ACP_CREATE_CLASS,
@@ -259,14 +361,28 @@ impl Attribute {
             Attribute::AcpReceiver => ATTR_ACP_RECEIVER,
             Attribute::AcpReceiverGroup => ATTR_ACP_RECEIVER_GROUP,
             Attribute::AcpSearchAttr => ATTR_ACP_SEARCH_ATTR,
+            Attribute::AcpStaged => ATTR_ACP_STAGED,
+            Attribute::AcpTargetGroup => ATTR_ACP_TARGET_GROUP,
             Attribute::AcpTargetScope => ATTR_ACP_TARGET_SCOPE,
+            Attribute::AnnouncementCli => ATTR_ANNOUNCEMENT_CLI,
+            Attribute::AnnouncementEnd => ATTR_ANNOUNCEMENT_END,
+            Attribute::AnnouncementMessage => ATTR_ANNOUNCEMENT_MESSAGE,
+            Attribute::AnnouncementSeverity => ATTR_ANNOUNCEMENT_SEVERITY,
+            Attribute::AnnouncementStart => ATTR_ANNOUNCEMENT_START,
             Attribute::ApiTokenSession => ATTR_API_TOKEN_SESSION,
             Attribute::ApplicationPassword => ATTR_APPLICATION_PASSWORD,
             Attribute::AttestedPasskeys => ATTR_ATTESTED_PASSKEYS,
             Attribute::Attr => ATTR_ATTR,
             Attribute::AttributeName => ATTR_ATTRIBUTENAME,
             Attribute::AttributeType => ATTR_ATTRIBUTETYPE,
+            Attribute::AuthLockoutDuration => ATTR_AUTH_LOCKOUT_DURATION,
+            Attribute::AuthLockoutThreshold => ATTR_AUTH_LOCKOUT_THRESHOLD,
+            Attribute::AuthLockoutWindow => ATTR_AUTH_LOCKOUT_WINDOW,
             Attribute::AuthSessionExpiry => ATTR_AUTH_SESSION_EXPIRY,
+            Attribute::AuthSessionIdleTimeout => ATTR_AUTH_SESSION_IDLE_TIMEOUT,
+            Attribute::AuthTrustedNetwork => ATTR_AUTH_TRUSTED_NETWORK,
+            Attribute::AuthPasswordHistoryCount => ATTR_AUTH_PASSWORD_HISTORY_COUNT,
+            Attribute::AuthPasswordMaximumAge => ATTR_AUTH_PASSWORD_MAXIMUM_AGE,
             Attribute::AuthPasswordMinimumLength => ATTR_AUTH_PASSWORD_MINIMUM_LENGTH,
             Attribute::BadlistPassword => ATTR_BADLIST_PASSWORD,
             Attribute::Certificate => ATTR_CERTIFICATE,
@@ -277,8 +393,13 @@ impl Attribute {
             Attribute::CookiePrivateKey => ATTR_COOKIE_PRIVATE_KEY,
             Attribute::CreatedAtCid => ATTR_CREATED_AT_CID,
             Attribute::CredentialUpdateIntentToken => ATTR_CREDENTIAL_UPDATE_INTENT_TOKEN,
+            Attribute::CredentialUsage => ATTR_CREDENTIAL_USAGE,
             Attribute::CredentialTypeMinimum => ATTR_CREDENTIAL_TYPE_MINIMUM,
+            Attribute::CredentialTypeMinimumUntrustedNetwork => {
+                ATTR_CREDENTIAL_TYPE_MINIMUM_UNTRUSTED_NETWORK
+            }
             Attribute::DeniedName => ATTR_DENIED_NAME,
+            Attribute::DeniedPasswordTerm => ATTR_DENIED_PASSWORD_TERM,
             Attribute::Description => ATTR_DESCRIPTION,
             Attribute::DirectMemberOf => ATTR_DIRECTMEMBEROF,
             Attribute::DisplayName => ATTR_DISPLAYNAME,
@@ -288,8 +409,11 @@ impl Attribute {
             Attribute::DomainDevelopmentTaint => ATTR_DOMAIN_DEVELOPMENT_TAINT,
             Attribute::DomainDisplayName => ATTR_DOMAIN_DISPLAY_NAME,
             Attribute::DomainLdapBasedn => ATTR_DOMAIN_LDAP_BASEDN,
+            Attribute::DomainLoginBanner => ATTR_DOMAIN_LOGIN_BANNER,
             Attribute::DomainName => ATTR_DOMAIN_NAME,
+            Attribute::DomainPrimaryColour => ATTR_DOMAIN_PRIMARY_COLOUR,
             Attribute::DomainSsid => ATTR_DOMAIN_SSID,
+            Attribute::DomainTheme => ATTR_DOMAIN_THEME,
             Attribute::DomainTokenKey => ATTR_DOMAIN_TOKEN_KEY,
             Attribute::DomainUuid => ATTR_DOMAIN_UUID,
             Attribute::DynGroup => ATTR_DYNGROUP,
@@ -308,12 +432,26 @@ impl Attribute {
             Attribute::GidNumber => ATTR_GIDNUMBER,
             Attribute::GrantUiHint => ATTR_GRANT_UI_HINT,
             Attribute::Group => ATTR_GROUP,
+            Attribute::HbacDeny => ATTR_HBAC_DENY,
+            Attribute::HbacHost => ATTR_HBAC_HOST,
+            Attribute::HbacHostGroup => ATTR_HBAC_HOST_GROUP,
+            Attribute::HbacMember => ATTR_HBAC_MEMBER,
+            Attribute::HbacService => ATTR_HBAC_SERVICE,
+            Attribute::HostGroupMember => ATTR_HOST_GROUP_MEMBER,
+            Attribute::HostJoinToken => ATTR_HOST_JOIN_TOKEN,
+            Attribute::HostJoinTokenExpiry => ATTR_HOST_JOIN_TOKEN_EXPIRY,
+            Attribute::HostKioskCacheTimeout => ATTR_HOST_KIOSK_CACHE_TIMEOUT,
+            Attribute::HostKioskGuestGroup => ATTR_HOST_KIOSK_GUEST_GROUP,
+            Attribute::HostKioskMode => ATTR_HOST_KIOSK_MODE,
+            Attribute::HostTag => ATTR_HOST_TAG,
             Attribute::IdVerificationEcKey => ATTR_ID_VERIFICATION_ECKEY,
             Attribute::Image => ATTR_IMAGE,
             Attribute::Index => ATTR_INDEX,
             Attribute::IpaNtHash => ATTR_IPANTHASH,
             Attribute::IpaSshPubKey => ATTR_IPASSHPUBKEY,
             Attribute::JwsEs256PrivateKey => ATTR_JWS_ES256_PRIVATE_KEY,
+            Attribute::JwtPublicKey => ATTR_JWT_PUBLIC_KEY,
+            Attribute::KerberosServicePrincipal => ATTR_KERBEROS_SERVICE_PRINCIPAL,
             Attribute::KeyActionRotate => ATTR_KEY_ACTION_ROTATE,
             Attribute::KeyActionRevoke => ATTR_KEY_ACTION_REVOKE,
             Attribute::KeyActionImportJwsEs256 => ATTR_KEY_ACTION_IMPORT_JWS_ES256,
@@ -321,6 +459,7 @@ impl Attribute {
             Attribute::KeyProvider => ATTR_KEY_PROVIDER,
             Attribute::LastModifiedCid => ATTR_LAST_MODIFIED_CID,
             Attribute::LdapAllowUnixPwBind => ATTR_LDAP_ALLOW_UNIX_PW_BIND,
+            Attribute::LdapGroupCompat => ATTR_LDAP_GROUP_COMPAT,
             Attribute::LdapEmailAddress => ATTR_LDAP_EMAIL_ADDRESS,
             Attribute::LdapKeys => ATTR_LDAP_KEYS,
             Attribute::LdapMaxQueryableAttrs => ATTR_LDAP_MAX_QUERYABLE_ATTRS,
@@ -329,42 +468,95 @@ impl Attribute {
             Attribute::LimitSearchMaxResults => ATTR_LIMIT_SEARCH_MAX_RESULTS,
             Attribute::LimitSearchMaxFilterTest => ATTR_LIMIT_SEARCH_MAX_FILTER_TEST,
             Attribute::LinkedGroup => ATTR_LINKEDGROUP,
+            Attribute::LoginHostTag => ATTR_LOGIN_HOST_TAG,
             Attribute::LoginShell => ATTR_LOGINSHELL,
             Attribute::Mail => ATTR_MAIL,
             Attribute::May => ATTR_MAY,
             Attribute::Member => ATTR_MEMBER,
             Attribute::MemberOf => ATTR_MEMBEROF,
+            Attribute::MemberValidUntil => ATTR_MEMBER_VALID_UNTIL,
+            Attribute::MembershipRequestable => ATTR_MEMBERSHIP_REQUESTABLE,
+            Attribute::MembershipRequestDecidedBy => ATTR_MEMBERSHIP_REQUEST_DECIDED_BY,
+            Attribute::MembershipRequestGroup => ATTR_MEMBERSHIP_REQUEST_GROUP,
+            Attribute::MembershipRequester => ATTR_MEMBERSHIP_REQUESTER,
+            Attribute::MembershipRequestStatus => ATTR_MEMBERSHIP_REQUEST_STATUS,
+            Attribute::MembershipRequestValidUntil => ATTR_MEMBERSHIP_REQUEST_VALID_UNTIL,
+            Attribute::MigrationAppliedAt => ATTR_MIGRATION_APPLIED_AT,
+            Attribute::MigrationDuration => ATTR_MIGRATION_DURATION,
+            Attribute::MigrationEntriesChanged => ATTR_MIGRATION_ENTRIES_CHANGED,
+            Attribute::MigrationFromLevel => ATTR_MIGRATION_FROM_LEVEL,
+            Attribute::MigrationName => ATTR_MIGRATION_NAME,
+            Attribute::MigrationToLevel => ATTR_MIGRATION_TO_LEVEL,
             Attribute::MultiValue => ATTR_MULTIVALUE,
             Attribute::Must => ATTR_MUST,
             Attribute::Name => ATTR_NAME,
             Attribute::NameHistory => ATTR_NAME_HISTORY,
             Attribute::NoIndex => ATTR_NO_INDEX,
+            Attribute::NotificationOptOut => ATTR_NOTIFICATION_OPT_OUT,
             Attribute::NsUniqueId => ATTR_NSUNIQUEID,
             Attribute::NsAccountLock => ATTR_NSACCOUNTLOCK,
             Attribute::OAuth2AllowInsecureClientDisablePkce => {
                 ATTR_OAUTH2_ALLOW_INSECURE_CLIENT_DISABLE_PKCE
             }
+            Attribute::OAuth2AllowInsecureRefreshTokenReuse => {
+                ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE
+            }
             Attribute::OAuth2AllowLocalhostRedirect => ATTR_OAUTH2_ALLOW_LOCALHOST_REDIRECT,
+            Attribute::OAuth2AllowedGrantTypes => ATTR_OAUTH2_ALLOWED_GRANT_TYPES,
+            Attribute::OAuth2AllowedResponseTypes => ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES,
             Attribute::OAuth2ConsentScopeMap => ATTR_OAUTH2_CONSENT_SCOPE_MAP,
             Attribute::OAuth2DeviceFlowEnable => ATTR_OAUTH2_DEVICE_FLOW_ENABLE,
             Attribute::OAuth2JwtLegacyCryptoEnable => ATTR_OAUTH2_JWT_LEGACY_CRYPTO_ENABLE,
+            Attribute::OAuth2JwtSignAlg => ATTR_OAUTH2_JWT_SIGN_ALG,
             Attribute::OAuth2PreferShortUsername => ATTR_OAUTH2_PREFER_SHORT_USERNAME,
+            Attribute::OAuth2RedirectUriMode => ATTR_OAUTH2_REDIRECT_URI_MODE,
+            Attribute::OAuth2RsAssignedMember => ATTR_OAUTH2_RS_ASSIGNED_MEMBER,
             Attribute::OAuth2RsBasicSecret => ATTR_OAUTH2_RS_BASIC_SECRET,
+            Attribute::OAuth2RsBasicSecretExpiry => ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY,
+            Attribute::OAuth2RsBasicSecretLastUsed => ATTR_OAUTH2_RS_BASIC_SECRET_LAST_USED,
+            Attribute::OAuth2RsBasicSecretPrevious => ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS,
+            Attribute::OAuth2RsBasicSecretPreviousExpiry => {
+                ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY
+            }
+            Attribute::OAuth2RsBasicSecretPreviousLastUsed => {
+                ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED
+            }
             Attribute::OAuth2RsClaimMap => ATTR_OAUTH2_RS_CLAIM_MAP,
             Attribute::OAuth2RsImplicitScopes => ATTR_OAUTH2_RS_IMPLICIT_SCOPES,
             Attribute::OAuth2RsName => ATTR_OAUTH2_RS_NAME,
             Attribute::OAuth2RsOrigin => ATTR_OAUTH2_RS_ORIGIN,
             Attribute::OAuth2RsOriginLanding => ATTR_OAUTH2_RS_ORIGIN_LANDING,
+            Attribute::OAuth2RsScopeDescription => ATTR_OAUTH2_RS_SCOPE_DESCRIPTION,
+            Attribute::OAuth2RsComputedClaim => ATTR_OAUTH2_RS_COMPUTED_CLAIM,
             Attribute::OAuth2RsScopeMap => ATTR_OAUTH2_RS_SCOPE_MAP,
             Attribute::OAuth2RsSupScopeMap => ATTR_OAUTH2_RS_SUP_SCOPE_MAP,
             Attribute::OAuth2RsTokenKey => ATTR_OAUTH2_RS_TOKEN_KEY,
             Attribute::OAuth2Session => ATTR_OAUTH2_SESSION,
             Attribute::OAuth2StrictRedirectUri => ATTR_OAUTH2_STRICT_REDIRECT_URI,
+            Attribute::OAuth2TokenExchangeAudience => ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE,
             Attribute::ObjectClass => ATTR_OBJECTCLASS,
+            Attribute::OidcUpstreamAuthorisationEndpoint => {
+                ATTR_OIDC_UPSTREAM_AUTHORISATION_ENDPOINT
+            }
+            Attribute::OidcUpstreamClientId => ATTR_OIDC_UPSTREAM_CLIENT_ID,
+            Attribute::OidcUpstreamClientSecret => ATTR_OIDC_UPSTREAM_CLIENT_SECRET,
+            Attribute::OidcUpstreamIssuer => ATTR_OIDC_UPSTREAM_ISSUER,
+            Attribute::OidcUpstreamJwksUri => ATTR_OIDC_UPSTREAM_JWKS_URI,
+            Attribute::OidcUpstreamTokenEndpoint => ATTR_OIDC_UPSTREAM_TOKEN_ENDPOINT,
             Attribute::OtherNoIndex => ATTR_OTHER_NO_INDEX,
+            Attribute::ParentGroup => ATTR_PARENT_GROUP,
             Attribute::PassKeys => ATTR_PASSKEYS,
+            Attribute::PasswordBreachCheck => ATTR_PASSWORD_BREACH_CHECK,
+            Attribute::PasswordHistory => ATTR_PASSWORD_HISTORY,
+            Attribute::PasswordLastChanged => ATTR_PASSWORD_LAST_CHANGED,
             Attribute::PasswordImport => ATTR_PASSWORD_IMPORT,
             Attribute::PatchLevel => ATTR_PATCH_LEVEL,
+            Attribute::Phone => ATTR_PHONE,
+            Attribute::PosixDefaultShell => ATTR_POSIX_DEFAULT_SHELL,
+            Attribute::PosixGecosFormat => ATTR_POSIX_GECOS_FORMAT,
+            Attribute::PosixHomeQuota => ATTR_POSIX_HOME_QUOTA,
+            Attribute::PosixHomeSkeleton => ATTR_POSIX_HOME_SKELETON,
+            Attribute::PosixHomeTemplate => ATTR_POSIX_HOME_TEMPLATE,
             Attribute::Phantom => ATTR_PHANTOM,
             Attribute::PrimaryCredential => ATTR_PRIMARY_CREDENTIAL,
             Attribute::PrivateCookieKey => ATTR_PRIVATE_COOKIE_KEY,
@@ -374,11 +566,18 @@ impl Attribute {
             Attribute::Refers => ATTR_REFERS,
             Attribute::Replicated => ATTR_REPLICATED,
             Attribute::Rs256PrivateKeyDer => ATTR_RS256_PRIVATE_KEY_DER,
+            Attribute::Saml2AcsUrl => ATTR_SAML2_ACS_URL,
+            Attribute::Saml2EntityId => ATTR_SAML2_ENTITY_ID,
+            Attribute::Saml2GroupMap => ATTR_SAML2_GROUP_MAP,
             Attribute::Scope => ATTR_SCOPE,
             Attribute::ScimSchemas => ATTR_SCIM_SCHEMAS,
             Attribute::SourceUuid => ATTR_SOURCE_UUID,
             Attribute::Spn => ATTR_SPN,
+            Attribute::Sponsor => ATTR_SPONSOR,
             Attribute::SshPublicKey => ATTR_SSH_PUBLICKEY,
+            Attribute::SshPublicKeyExpiry => ATTR_SSH_PUBLICKEY_EXPIRY,
+            Attribute::SshKeyAllowedType => ATTR_SSH_KEY_ALLOWED_TYPE,
+            Attribute::SshKeyRsaMinimumBits => ATTR_SSH_KEY_RSA_MINIMUM_BITS,
             Attribute::SudoHost => ATTR_SUDOHOST,
             Attribute::Supplements => ATTR_SUPPLEMENTS,
             Attribute::SyncAllowed => ATTR_SYNC_ALLOWED,
@@ -389,13 +588,16 @@ impl Attribute {
             Attribute::SyncParentUuid => ATTR_SYNC_PARENT_UUID,
             Attribute::SyncTokenSession => ATTR_SYNC_TOKEN_SESSION,
             Attribute::SyncYieldAuthority => ATTR_SYNC_YIELD_AUTHORITY,
+            Attribute::SyncConflict => ATTR_SYNC_CONFLICT,
             Attribute::Syntax => ATTR_SYNTAX,
             Attribute::SystemExcludes => ATTR_SYSTEMEXCLUDES,
             Attribute::SystemMay => ATTR_SYSTEMMAY,
             Attribute::SystemMust => ATTR_SYSTEMMUST,
             Attribute::SystemSupplements => ATTR_SYSTEMSUPPLEMENTS,
+            Attribute::TelephoneNumber => ATTR_TELEPHONE_NUMBER,
             Attribute::Term => ATTR_TERM,
             Attribute::TotpImport => ATTR_TOTP_IMPORT,
+            Attribute::UiTheme => ATTR_UI_THEME,
             Attribute::Uid => ATTR_UID,
             Attribute::UidNumber => ATTR_UIDNUMBER,
             Attribute::Unique => ATTR_UNIQUE,
@@ -407,7 +609,15 @@ impl Attribute {
             Attribute::Uuid => ATTR_UUID,
             Attribute::Version => ATTR_VERSION,
             Attribute::WebauthnAttestationCaList => ATTR_WEBAUTHN_ATTESTATION_CA_LIST,
+            Attribute::WebhookFilter => ATTR_WEBHOOK_FILTER,
+            Attribute::WebhookSecret => ATTR_WEBHOOK_SECRET,
+            Attribute::WebhookUrl => ATTR_WEBHOOK_URL,
+            Attribute::WhitePagesRateLimit => ATTR_WHITE_PAGES_RATE_LIMIT,
+            Attribute::WhitePagesTrustedNetwork => ATTR_WHITE_PAGES_TRUSTED_NETWORK,
+            Attribute::AllowApiTokens => ATTR_ALLOW_API_TOKENS,
+            Attribute::AllowEmailLink => ATTR_ALLOW_EMAIL_LINK,
             Attribute::AllowPrimaryCredFallback => ATTR_ALLOW_PRIMARY_CRED_FALLBACK,
+            Attribute::AllowUnixPassword => ATTR_ALLOW_UNIX_PASSWORD,
 
             #[cfg(any(debug_assertions, test, feature = "test"))]
             Attribute::NonExist => TEST_ATTR_NON_EXIST,
@@ -432,7 +642,9 @@ impl Attribute {
         // to limit length of str?
         match value.to_lowercase().as_str() {
             ATTR_ACCOUNT => Attribute::Account,
+            ATTR_ACCOUNT_ARCHIVE_DELAY => Attribute::AccountArchiveDelay,
             ATTR_ACCOUNT_EXPIRE => Attribute::AccountExpire,
+            ATTR_ACCOUNT_LIFETIME => Attribute::AccountLifetime,
             ATTR_ACCOUNT_VALID_FROM => Attribute::AccountValidFrom,
             ATTR_ACP_CREATE_ATTR => Attribute::AcpCreateAttr,
             ATTR_ACP_CREATE_CLASS => Attribute::AcpCreateClass,
@@ -443,14 +655,28 @@ impl Attribute {
             ATTR_ACP_RECEIVER => Attribute::AcpReceiver,
             ATTR_ACP_RECEIVER_GROUP => Attribute::AcpReceiverGroup,
             ATTR_ACP_SEARCH_ATTR => Attribute::AcpSearchAttr,
+            ATTR_ACP_STAGED => Attribute::AcpStaged,
+            ATTR_ACP_TARGET_GROUP => Attribute::AcpTargetGroup,
             ATTR_ACP_TARGET_SCOPE => Attribute::AcpTargetScope,
+            ATTR_ANNOUNCEMENT_CLI => Attribute::AnnouncementCli,
+            ATTR_ANNOUNCEMENT_END => Attribute::AnnouncementEnd,
+            ATTR_ANNOUNCEMENT_MESSAGE => Attribute::AnnouncementMessage,
+            ATTR_ANNOUNCEMENT_SEVERITY => Attribute::AnnouncementSeverity,
+            ATTR_ANNOUNCEMENT_START => Attribute::AnnouncementStart,
             ATTR_API_TOKEN_SESSION => Attribute::ApiTokenSession,
             ATTR_APPLICATION_PASSWORD => Attribute::ApplicationPassword,
             ATTR_ATTESTED_PASSKEYS => Attribute::AttestedPasskeys,
             ATTR_ATTR => Attribute::Attr,
             ATTR_ATTRIBUTENAME => Attribute::AttributeName,
             ATTR_ATTRIBUTETYPE => Attribute::AttributeType,
+            ATTR_AUTH_LOCKOUT_DURATION => Attribute::AuthLockoutDuration,
+            ATTR_AUTH_LOCKOUT_THRESHOLD => Attribute::AuthLockoutThreshold,
+            ATTR_AUTH_LOCKOUT_WINDOW => Attribute::AuthLockoutWindow,
             ATTR_AUTH_SESSION_EXPIRY => Attribute::AuthSessionExpiry,
+            ATTR_AUTH_SESSION_IDLE_TIMEOUT => Attribute::AuthSessionIdleTimeout,
+            ATTR_AUTH_TRUSTED_NETWORK => Attribute::AuthTrustedNetwork,
+            ATTR_AUTH_PASSWORD_HISTORY_COUNT => Attribute::AuthPasswordHistoryCount,
+            ATTR_AUTH_PASSWORD_MAXIMUM_AGE => Attribute::AuthPasswordMaximumAge,
             ATTR_AUTH_PASSWORD_MINIMUM_LENGTH => Attribute::AuthPasswordMinimumLength,
             ATTR_BADLIST_PASSWORD => Attribute::BadlistPassword,
             ATTR_CERTIFICATE => Attribute::Certificate,
@@ -461,8 +687,13 @@ impl Attribute {
             ATTR_COOKIE_PRIVATE_KEY => Attribute::CookiePrivateKey,
             ATTR_CREATED_AT_CID => Attribute::CreatedAtCid,
             ATTR_CREDENTIAL_UPDATE_INTENT_TOKEN => Attribute::CredentialUpdateIntentToken,
+            ATTR_CREDENTIAL_USAGE => Attribute::CredentialUsage,
             ATTR_CREDENTIAL_TYPE_MINIMUM => Attribute::CredentialTypeMinimum,
+            ATTR_CREDENTIAL_TYPE_MINIMUM_UNTRUSTED_NETWORK => {
+                Attribute::CredentialTypeMinimumUntrustedNetwork
+            }
             ATTR_DENIED_NAME => Attribute::DeniedName,
+            ATTR_DENIED_PASSWORD_TERM => Attribute::DeniedPasswordTerm,
             ATTR_DESCRIPTION => Attribute::Description,
             ATTR_DIRECTMEMBEROF => Attribute::DirectMemberOf,
             ATTR_DISPLAYNAME => Attribute::DisplayName,
@@ -472,8 +703,11 @@ impl Attribute {
             ATTR_DOMAIN_DISPLAY_NAME => Attribute::DomainDisplayName,
             ATTR_DOMAIN_DEVELOPMENT_TAINT => Attribute::DomainDevelopmentTaint,
             ATTR_DOMAIN_LDAP_BASEDN => Attribute::DomainLdapBasedn,
+            ATTR_DOMAIN_LOGIN_BANNER => Attribute::DomainLoginBanner,
             ATTR_DOMAIN_NAME => Attribute::DomainName,
+            ATTR_DOMAIN_PRIMARY_COLOUR => Attribute::DomainPrimaryColour,
             ATTR_DOMAIN_SSID => Attribute::DomainSsid,
+            ATTR_DOMAIN_THEME => Attribute::DomainTheme,
             ATTR_DOMAIN_TOKEN_KEY => Attribute::DomainTokenKey,
             ATTR_DOMAIN_UUID => Attribute::DomainUuid,
             ATTR_DYNGROUP => Attribute::DynGroup,
@@ -492,12 +726,26 @@ impl Attribute {
             ATTR_GIDNUMBER => Attribute::GidNumber,
             ATTR_GRANT_UI_HINT => Attribute::GrantUiHint,
             ATTR_GROUP => Attribute::Group,
+            ATTR_HBAC_DENY => Attribute::HbacDeny,
+            ATTR_HBAC_HOST => Attribute::HbacHost,
+            ATTR_HBAC_HOST_GROUP => Attribute::HbacHostGroup,
+            ATTR_HBAC_MEMBER => Attribute::HbacMember,
+            ATTR_HBAC_SERVICE => Attribute::HbacService,
+            ATTR_HOST_GROUP_MEMBER => Attribute::HostGroupMember,
+            ATTR_HOST_JOIN_TOKEN => Attribute::HostJoinToken,
+            ATTR_HOST_JOIN_TOKEN_EXPIRY => Attribute::HostJoinTokenExpiry,
+            ATTR_HOST_KIOSK_CACHE_TIMEOUT => Attribute::HostKioskCacheTimeout,
+            ATTR_HOST_KIOSK_GUEST_GROUP => Attribute::HostKioskGuestGroup,
+            ATTR_HOST_KIOSK_MODE => Attribute::HostKioskMode,
+            ATTR_HOST_TAG => Attribute::HostTag,
             ATTR_ID_VERIFICATION_ECKEY => Attribute::IdVerificationEcKey,
             ATTR_IMAGE => Attribute::Image,
             ATTR_INDEX => Attribute::Index,
             ATTR_IPANTHASH => Attribute::IpaNtHash,
             ATTR_IPASSHPUBKEY => Attribute::IpaSshPubKey,
             ATTR_JWS_ES256_PRIVATE_KEY => Attribute::JwsEs256PrivateKey,
+            ATTR_JWT_PUBLIC_KEY => Attribute::JwtPublicKey,
+            ATTR_KERBEROS_SERVICE_PRINCIPAL => Attribute::KerberosServicePrincipal,
             ATTR_KEY_ACTION_ROTATE => Attribute::KeyActionRotate,
             ATTR_KEY_ACTION_REVOKE => Attribute::KeyActionRevoke,
             ATTR_KEY_ACTION_IMPORT_JWS_ES256 => Attribute::KeyActionImportJwsEs256,
@@ -505,12 +753,17 @@ impl Attribute {
             ATTR_KEY_PROVIDER => Attribute::KeyProvider,
             ATTR_LAST_MODIFIED_CID => Attribute::LastModifiedCid,
             ATTR_LDAP_ALLOW_UNIX_PW_BIND => Attribute::LdapAllowUnixPwBind,
+            ATTR_LDAP_GROUP_COMPAT => Attribute::LdapGroupCompat,
             ATTR_LDAP_EMAIL_ADDRESS => Attribute::LdapEmailAddress,
             ATTR_LDAP_KEYS => Attribute::LdapKeys,
             ATTR_LDAP_MAX_QUERYABLE_ATTRS => Attribute::LdapMaxQueryableAttrs,
             ATTR_SSH_PUBLICKEY => Attribute::SshPublicKey,
+            ATTR_SSH_PUBLICKEY_EXPIRY => Attribute::SshPublicKeyExpiry,
+            ATTR_SSH_KEY_ALLOWED_TYPE => Attribute::SshKeyAllowedType,
+            ATTR_SSH_KEY_RSA_MINIMUM_BITS => Attribute::SshKeyRsaMinimumBits,
             ATTR_LEGALNAME => Attribute::LegalName,
             ATTR_LINKEDGROUP => Attribute::LinkedGroup,
+            ATTR_LOGIN_HOST_TAG => Attribute::LoginHostTag,
             ATTR_LOGINSHELL => Attribute::LoginShell,
             ATTR_LIMIT_SEARCH_MAX_RESULTS => Attribute::LimitSearchMaxResults,
             ATTR_LIMIT_SEARCH_MAX_FILTER_TEST => Attribute::LimitSearchMaxFilterTest,
@@ -518,37 +771,89 @@ impl Attribute {
             ATTR_MAY => Attribute::May,
             ATTR_MEMBER => Attribute::Member,
             ATTR_MEMBEROF => Attribute::MemberOf,
+            ATTR_MEMBER_VALID_UNTIL => Attribute::MemberValidUntil,
+            ATTR_MEMBERSHIP_REQUESTABLE => Attribute::MembershipRequestable,
+            ATTR_MEMBERSHIP_REQUEST_DECIDED_BY => Attribute::MembershipRequestDecidedBy,
+            ATTR_MEMBERSHIP_REQUEST_GROUP => Attribute::MembershipRequestGroup,
+            ATTR_MEMBERSHIP_REQUESTER => Attribute::MembershipRequester,
+            ATTR_MEMBERSHIP_REQUEST_STATUS => Attribute::MembershipRequestStatus,
+            ATTR_MEMBERSHIP_REQUEST_VALID_UNTIL => Attribute::MembershipRequestValidUntil,
+            ATTR_MIGRATION_APPLIED_AT => Attribute::MigrationAppliedAt,
+            ATTR_MIGRATION_DURATION => Attribute::MigrationDuration,
+            ATTR_MIGRATION_ENTRIES_CHANGED => Attribute::MigrationEntriesChanged,
+            ATTR_MIGRATION_FROM_LEVEL => Attribute::MigrationFromLevel,
+            ATTR_MIGRATION_NAME => Attribute::MigrationName,
+            ATTR_MIGRATION_TO_LEVEL => Attribute::MigrationToLevel,
             ATTR_MULTIVALUE => Attribute::MultiValue,
             ATTR_MUST => Attribute::Must,
             ATTR_NAME => Attribute::Name,
             ATTR_NAME_HISTORY => Attribute::NameHistory,
             ATTR_NO_INDEX => Attribute::NoIndex,
+            ATTR_NOTIFICATION_OPT_OUT => Attribute::NotificationOptOut,
             ATTR_NSUNIQUEID => Attribute::NsUniqueId,
             ATTR_NSACCOUNTLOCK => Attribute::NsAccountLock,
             ATTR_OAUTH2_ALLOW_INSECURE_CLIENT_DISABLE_PKCE => {
                 Attribute::OAuth2AllowInsecureClientDisablePkce
             }
+            ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE => {
+                Attribute::OAuth2AllowInsecureRefreshTokenReuse
+            }
             ATTR_OAUTH2_ALLOW_LOCALHOST_REDIRECT => Attribute::OAuth2AllowLocalhostRedirect,
+            ATTR_OAUTH2_ALLOWED_GRANT_TYPES => Attribute::OAuth2AllowedGrantTypes,
+            ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES => Attribute::OAuth2AllowedResponseTypes,
             ATTR_OAUTH2_CONSENT_SCOPE_MAP => Attribute::OAuth2ConsentScopeMap,
             ATTR_OAUTH2_DEVICE_FLOW_ENABLE => Attribute::OAuth2DeviceFlowEnable,
             ATTR_OAUTH2_JWT_LEGACY_CRYPTO_ENABLE => Attribute::OAuth2JwtLegacyCryptoEnable,
+            ATTR_OAUTH2_JWT_SIGN_ALG => Attribute::OAuth2JwtSignAlg,
             ATTR_OAUTH2_PREFER_SHORT_USERNAME => Attribute::OAuth2PreferShortUsername,
+            ATTR_OAUTH2_REDIRECT_URI_MODE => Attribute::OAuth2RedirectUriMode,
+            ATTR_OAUTH2_RS_ASSIGNED_MEMBER => Attribute::OAuth2RsAssignedMember,
             ATTR_OAUTH2_RS_BASIC_SECRET => Attribute::OAuth2RsBasicSecret,
+            ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY => Attribute::OAuth2RsBasicSecretExpiry,
+            ATTR_OAUTH2_RS_BASIC_SECRET_LAST_USED => Attribute::OAuth2RsBasicSecretLastUsed,
+            ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS => Attribute::OAuth2RsBasicSecretPrevious,
+            ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY => {
+                Attribute::OAuth2RsBasicSecretPreviousExpiry
+            }
+            ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED => {
+                Attribute::OAuth2RsBasicSecretPreviousLastUsed
+            }
             ATTR_OAUTH2_RS_CLAIM_MAP => Attribute::OAuth2RsClaimMap,
             ATTR_OAUTH2_RS_IMPLICIT_SCOPES => Attribute::OAuth2RsImplicitScopes,
             ATTR_OAUTH2_RS_NAME => Attribute::OAuth2RsName,
             ATTR_OAUTH2_RS_ORIGIN => Attribute::OAuth2RsOrigin,
             ATTR_OAUTH2_RS_ORIGIN_LANDING => Attribute::OAuth2RsOriginLanding,
+            ATTR_OAUTH2_RS_SCOPE_DESCRIPTION => Attribute::OAuth2RsScopeDescription,
+            ATTR_OAUTH2_RS_COMPUTED_CLAIM => Attribute::OAuth2RsComputedClaim,
             ATTR_OAUTH2_RS_SCOPE_MAP => Attribute::OAuth2RsScopeMap,
             ATTR_OAUTH2_RS_SUP_SCOPE_MAP => Attribute::OAuth2RsSupScopeMap,
             ATTR_OAUTH2_RS_TOKEN_KEY => Attribute::OAuth2RsTokenKey,
             ATTR_OAUTH2_SESSION => Attribute::OAuth2Session,
             ATTR_OAUTH2_STRICT_REDIRECT_URI => Attribute::OAuth2StrictRedirectUri,
+            ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE => Attribute::OAuth2TokenExchangeAudience,
             ATTR_OBJECTCLASS => Attribute::ObjectClass,
+            ATTR_OIDC_UPSTREAM_AUTHORISATION_ENDPOINT => {
+                Attribute::OidcUpstreamAuthorisationEndpoint
+            }
+            ATTR_OIDC_UPSTREAM_CLIENT_ID => Attribute::OidcUpstreamClientId,
+            ATTR_OIDC_UPSTREAM_CLIENT_SECRET => Attribute::OidcUpstreamClientSecret,
+            ATTR_OIDC_UPSTREAM_ISSUER => Attribute::OidcUpstreamIssuer,
+            ATTR_OIDC_UPSTREAM_JWKS_URI => Attribute::OidcUpstreamJwksUri,
+            ATTR_OIDC_UPSTREAM_TOKEN_ENDPOINT => Attribute::OidcUpstreamTokenEndpoint,
             ATTR_OTHER_NO_INDEX => Attribute::OtherNoIndex,
+            ATTR_PARENT_GROUP => Attribute::ParentGroup,
             ATTR_PASSKEYS => Attribute::PassKeys,
+            ATTR_PASSWORD_BREACH_CHECK => Attribute::PasswordBreachCheck,
+            ATTR_PASSWORD_HISTORY => Attribute::PasswordHistory,
+            ATTR_PASSWORD_LAST_CHANGED => Attribute::PasswordLastChanged,
             ATTR_PASSWORD_IMPORT => Attribute::PasswordImport,
             ATTR_PATCH_LEVEL => Attribute::PatchLevel,
+            ATTR_PHONE => Attribute::Phone,
+            ATTR_POSIX_DEFAULT_SHELL => Attribute::PosixDefaultShell,
+            ATTR_POSIX_GECOS_FORMAT => Attribute::PosixGecosFormat,
+            ATTR_POSIX_HOME_QUOTA => Attribute::PosixHomeQuota,
+            ATTR_POSIX_HOME_SKELETON => Attribute::PosixHomeSkeleton,
+            ATTR_POSIX_HOME_TEMPLATE => Attribute::PosixHomeTemplate,
             ATTR_PHANTOM => Attribute::Phantom,
             ATTR_PRIMARY_CREDENTIAL => Attribute::PrimaryCredential,
             ATTR_PRIVATE_COOKIE_KEY => Attribute::PrivateCookieKey,
@@ -558,10 +863,14 @@ impl Attribute {
             ATTR_REFERS => Attribute::Refers,
             ATTR_REPLICATED => Attribute::Replicated,
             ATTR_RS256_PRIVATE_KEY_DER => Attribute::Rs256PrivateKeyDer,
+            ATTR_SAML2_ACS_URL => Attribute::Saml2AcsUrl,
+            ATTR_SAML2_ENTITY_ID => Attribute::Saml2EntityId,
+            ATTR_SAML2_GROUP_MAP => Attribute::Saml2GroupMap,
             ATTR_SCIM_SCHEMAS => Attribute::ScimSchemas,
             ATTR_SCOPE => Attribute::Scope,
             ATTR_SOURCE_UUID => Attribute::SourceUuid,
             ATTR_SPN => Attribute::Spn,
+            ATTR_SPONSOR => Attribute::Sponsor,
             ATTR_LDAP_SSHPUBLICKEY => Attribute::LdapSshPublicKey,
             ATTR_SUDOHOST => Attribute::SudoHost,
             ATTR_SUPPLEMENTS => Attribute::Supplements,
@@ -573,13 +882,16 @@ impl Attribute {
             ATTR_SYNC_PARENT_UUID => Attribute::SyncParentUuid,
             ATTR_SYNC_TOKEN_SESSION => Attribute::SyncTokenSession,
             ATTR_SYNC_YIELD_AUTHORITY => Attribute::SyncYieldAuthority,
+            ATTR_SYNC_CONFLICT => Attribute::SyncConflict,
             ATTR_SYNTAX => Attribute::Syntax,
             ATTR_SYSTEMEXCLUDES => Attribute::SystemExcludes,
             ATTR_SYSTEMMAY => Attribute::SystemMay,
             ATTR_SYSTEMMUST => Attribute::SystemMust,
             ATTR_SYSTEMSUPPLEMENTS => Attribute::SystemSupplements,
+            ATTR_TELEPHONE_NUMBER => Attribute::TelephoneNumber,
             ATTR_TERM => Attribute::Term,
             ATTR_TOTP_IMPORT => Attribute::TotpImport,
+            ATTR_UI_THEME => Attribute::UiTheme,
             ATTR_UID => Attribute::Uid,
             ATTR_UIDNUMBER => Attribute::UidNumber,
             ATTR_UNIQUE => Attribute::Unique,
@@ -591,7 +903,15 @@ impl Attribute {
             ATTR_UUID => Attribute::Uuid,
             ATTR_VERSION => Attribute::Version,
             ATTR_WEBAUTHN_ATTESTATION_CA_LIST => Attribute::WebauthnAttestationCaList,
+            ATTR_WEBHOOK_FILTER => Attribute::WebhookFilter,
+            ATTR_WEBHOOK_SECRET => Attribute::WebhookSecret,
+            ATTR_WEBHOOK_URL => Attribute::WebhookUrl,
+            ATTR_WHITE_PAGES_RATE_LIMIT => Attribute::WhitePagesRateLimit,
+            ATTR_WHITE_PAGES_TRUSTED_NETWORK => Attribute::WhitePagesTrustedNetwork,
+            ATTR_ALLOW_API_TOKENS => Attribute::AllowApiTokens,
+            ATTR_ALLOW_EMAIL_LINK => Attribute::AllowEmailLink,
             ATTR_ALLOW_PRIMARY_CRED_FALLBACK => Attribute::AllowPrimaryCredFallback,
+            ATTR_ALLOW_UNIX_PASSWORD => Attribute::AllowUnixPassword,
 
             #[cfg(any(debug_assertions, test, feature = "test"))]
             TEST_ATTR_NON_EXIST => Attribute::NonExist,
@@ -28,6 +28,7 @@ pub const VALID_IMAGE_UPLOAD_CONTENT_TYPES: [&str; 5] = [
 ];
 
 pub const APPLICATION_JSON: &str = "application/json";
+pub const APPLICATION_PROBLEM_JSON: &str = "application/problem+json";
 
 /// The "system" path for Kanidm client config
 pub const DEFAULT_CLIENT_CONFIG_PATH: &str = env!("KANIDM_CLIENT_CONFIG_PATH");
@@ -48,6 +49,13 @@ pub const DEFAULT_REPLICATION_ORIGIN: &str = "repl://localhost:8444";
 /// Default replication poll window in seconds.
 pub const DEFAULT_REPL_TASK_POLL_INTERVAL: u64 = 15;
 
+/// Default clock skew in seconds between replication partners before a warning is raised.
+pub const DEFAULT_REPL_CLOCK_SKEW_WARNING: u64 = 5;
+
+/// Default clock skew in seconds between replication partners before replication with
+/// that partner is refused.
+pub const DEFAULT_REPL_CLOCK_SKEW_TOLERANCE: u64 = 120;
+
 /// Default grace window for authentication tokens. This allows a token to be
 /// validated by another replica before the backing database session has been
 /// replicated to the partner. If replication stalls until this point then
@@ -55,7 +63,9 @@ pub const DEFAULT_REPL_TASK_POLL_INTERVAL: u64 = 15;
 pub const AUTH_TOKEN_GRACE_WINDOW: Duration = Duration::from_secs(5 * 60);
 
 // IF YOU CHANGE THESE VALUES YOU BREAK EVERYTHING
+pub const ATTR_ACCOUNT_ARCHIVE_DELAY: &str = "account_archive_delay";
 pub const ATTR_ACCOUNT_EXPIRE: &str = "account_expire";
+pub const ATTR_ACCOUNT_LIFETIME: &str = "account_lifetime";
 pub const ATTR_ACCOUNT_VALID_FROM: &str = "account_valid_from";
 pub const ATTR_ACCOUNT: &str = "account";
 pub const ATTR_ACP_CREATE_ATTR: &str = "acp_create_attr";
@@ -67,14 +77,28 @@ pub const ATTR_ACP_MODIFY_REMOVEDATTR: &str = "acp_modify_removedattr";
 pub const ATTR_ACP_RECEIVER_GROUP: &str = "acp_receiver_group";
 pub const ATTR_ACP_RECEIVER: &str = "acp_receiver";
 pub const ATTR_ACP_SEARCH_ATTR: &str = "acp_search_attr";
+pub const ATTR_ACP_STAGED: &str = "acp_staged";
+pub const ATTR_ACP_TARGET_GROUP: &str = "acp_target_group";
 pub const ATTR_ACP_TARGET_SCOPE: &str = "acp_targetscope";
+pub const ATTR_ANNOUNCEMENT_CLI: &str = "announcement_cli";
+pub const ATTR_ANNOUNCEMENT_END: &str = "announcement_end";
+pub const ATTR_ANNOUNCEMENT_MESSAGE: &str = "announcement_message";
+pub const ATTR_ANNOUNCEMENT_SEVERITY: &str = "announcement_severity";
+pub const ATTR_ANNOUNCEMENT_START: &str = "announcement_start";
 pub const ATTR_API_TOKEN_SESSION: &str = "api_token_session";
 pub const ATTR_APPLICATION_PASSWORD: &str = "application_password";
 pub const ATTR_ATTESTED_PASSKEYS: &str = "attested_passkeys";
 pub const ATTR_ATTR: &str = "attr";
 pub const ATTR_ATTRIBUTENAME: &str = "attributename";
 pub const ATTR_ATTRIBUTETYPE: &str = "attributetype";
+pub const ATTR_AUTH_LOCKOUT_DURATION: &str = "auth_lockout_duration";
+pub const ATTR_AUTH_LOCKOUT_THRESHOLD: &str = "auth_lockout_threshold";
+pub const ATTR_AUTH_LOCKOUT_WINDOW: &str = "auth_lockout_window";
 pub const ATTR_AUTH_SESSION_EXPIRY: &str = "authsession_expiry";
+pub const ATTR_AUTH_SESSION_IDLE_TIMEOUT: &str = "authsession_idle_timeout";
+pub const ATTR_AUTH_TRUSTED_NETWORK: &str = "auth_trusted_network";
+pub const ATTR_AUTH_PASSWORD_HISTORY_COUNT: &str = "auth_password_history_count";
+pub const ATTR_AUTH_PASSWORD_MAXIMUM_AGE: &str = "auth_password_maximum_age";
 pub const ATTR_AUTH_PASSWORD_MINIMUM_LENGTH: &str = "auth_password_minimum_length";
 pub const ATTR_BADLIST_PASSWORD: &str = "badlist_password";
 pub const ATTR_CERTIFICATE: &str = "certificate";
@@ -85,8 +109,12 @@ pub const ATTR_CN: &str = "cn";
 pub const ATTR_COOKIE_PRIVATE_KEY: &str = "cookie_private_key";
 pub const ATTR_CREATED_AT_CID: &str = "created_at_cid";
 pub const ATTR_CREDENTIAL_UPDATE_INTENT_TOKEN: &str = "credential_update_intent_token";
+pub const ATTR_CREDENTIAL_USAGE: &str = "credential_usage";
 pub const ATTR_CREDENTIAL_TYPE_MINIMUM: &str = "credential_type_minimum";
+pub const ATTR_CREDENTIAL_TYPE_MINIMUM_UNTRUSTED_NETWORK: &str =
+    "credential_type_minimum_untrusted_network";
 pub const ATTR_DENIED_NAME: &str = "denied_name";
+pub const ATTR_DENIED_PASSWORD_TERM: &str = "denied_password_term";
 pub const ATTR_DESCRIPTION: &str = "description";
 pub const ATTR_DIRECTMEMBEROF: &str = "directmemberof";
 pub const ATTR_DISPLAYNAME: &str = "displayname";
@@ -95,8 +123,11 @@ pub const ATTR_DOMAIN_ALLOW_EASTER_EGGS: &str = "domain_allow_easter_eggs";
 pub const ATTR_DOMAIN_DEVELOPMENT_TAINT: &str = "domain_development_taint";
 pub const ATTR_DOMAIN_DISPLAY_NAME: &str = "domain_display_name";
 pub const ATTR_DOMAIN_LDAP_BASEDN: &str = "domain_ldap_basedn";
+pub const ATTR_DOMAIN_LOGIN_BANNER: &str = "domain_login_banner";
 pub const ATTR_DOMAIN_NAME: &str = "domain_name";
+pub const ATTR_DOMAIN_PRIMARY_COLOUR: &str = "domain_primary_colour";
 pub const ATTR_DOMAIN_SSID: &str = "domain_ssid";
+pub const ATTR_DOMAIN_THEME: &str = "domain_theme";
 pub const ATTR_DOMAIN_TOKEN_KEY: &str = "domain_token_key";
 pub const ATTR_DOMAIN_UUID: &str = "domain_uuid";
 pub const ATTR_DOMAIN: &str = "domain";
@@ -121,12 +152,26 @@ pub const ATTR_GECOS: &str = "gecos";
 pub const ATTR_GIDNUMBER: &str = "gidnumber";
 pub const ATTR_GRANT_UI_HINT: &str = "grant_ui_hint";
 pub const ATTR_GROUP: &str = "group";
+pub const ATTR_HBAC_DENY: &str = "hbac_deny";
+pub const ATTR_HBAC_HOST: &str = "hbac_host";
+pub const ATTR_HBAC_HOST_GROUP: &str = "hbac_host_group";
+pub const ATTR_HBAC_MEMBER: &str = "hbac_member";
+pub const ATTR_HBAC_SERVICE: &str = "hbac_service";
+pub const ATTR_HOST_GROUP_MEMBER: &str = "host_group_member";
+pub const ATTR_HOST_JOIN_TOKEN: &str = "host_join_token";
+pub const ATTR_HOST_JOIN_TOKEN_EXPIRY: &str = "host_join_token_expiry";
+pub const ATTR_HOST_KIOSK_CACHE_TIMEOUT: &str = "host_kiosk_cache_timeout";
+pub const ATTR_HOST_KIOSK_GUEST_GROUP: &str = "host_kiosk_guest_group";
+pub const ATTR_HOST_KIOSK_MODE: &str = "host_kiosk_mode";
+pub const ATTR_HOST_TAG: &str = "host_tag";
 pub const ATTR_ID_VERIFICATION_ECKEY: &str = "id_verification_eckey";
 pub const ATTR_IMAGE: &str = "image";
 pub const ATTR_INDEX: &str = "index";
 pub const ATTR_IPANTHASH: &str = "ipanthash";
 pub const ATTR_IPASSHPUBKEY: &str = "ipasshpubkey";
 pub const ATTR_JWS_ES256_PRIVATE_KEY: &str = "jws_es256_private_key";
+pub const ATTR_JWT_PUBLIC_KEY: &str = "jwt_public_key";
+pub const ATTR_KERBEROS_SERVICE_PRINCIPAL: &str = "kerberos_service_principal";
 pub const ATTR_KEY_ACTION_ROTATE: &str = "key_action_rotate";
 pub const ATTR_KEY_ACTION_REVOKE: &str = "key_action_revoke";
 pub const ATTR_KEY_ACTION_IMPORT_JWS_ES256: &str = "key_action_import_jws_es256";
@@ -134,44 +179,93 @@ pub const ATTR_KEY_INTERNAL_DATA: &str = "key_internal_data";
 pub const ATTR_KEY_PROVIDER: &str = "key_provider";
 pub const ATTR_LAST_MODIFIED_CID: &str = "last_modified_cid";
 pub const ATTR_LDAP_ALLOW_UNIX_PW_BIND: &str = "ldap_allow_unix_pw_bind";
+pub const ATTR_LDAP_GROUP_COMPAT: &str = "ldap_group_compat";
 pub const ATTR_LEGALNAME: &str = "legalname";
 pub const ATTR_LINKEDGROUP: &str = "linked_group";
+pub const ATTR_LOGIN_HOST_TAG: &str = "login_host_tag";
 pub const ATTR_LOGINSHELL: &str = "loginshell";
 pub const ATTR_MAIL: &str = "mail";
 pub const ATTR_MAY: &str = "may";
 pub const ATTR_MEMBER: &str = "member";
 pub const ATTR_MEMBEROF: &str = "memberof";
+pub const ATTR_MEMBER_VALID_UNTIL: &str = "member_valid_until";
+pub const ATTR_MEMBERSHIP_REQUESTABLE: &str = "membership_requestable";
+pub const ATTR_MEMBERSHIP_REQUEST_DECIDED_BY: &str = "membership_request_decided_by";
+pub const ATTR_MEMBERSHIP_REQUEST_GROUP: &str = "membership_request_group";
+pub const ATTR_MEMBERSHIP_REQUESTER: &str = "membership_requester";
+pub const ATTR_MEMBERSHIP_REQUEST_STATUS: &str = "membership_request_status";
+pub const ATTR_MEMBERSHIP_REQUEST_VALID_UNTIL: &str = "membership_request_valid_until";
+pub const ATTR_MIGRATION_APPLIED_AT: &str = "migration_applied_at";
+pub const ATTR_MIGRATION_DURATION: &str = "migration_duration";
+pub const ATTR_MIGRATION_ENTRIES_CHANGED: &str = "migration_entries_changed";
+pub const ATTR_MIGRATION_FROM_LEVEL: &str = "migration_from_level";
+pub const ATTR_MIGRATION_NAME: &str = "migration_name";
+pub const ATTR_MIGRATION_TO_LEVEL: &str = "migration_to_level";
 pub const ATTR_MULTIVALUE: &str = "multivalue";
 pub const ATTR_MUST: &str = "must";
 pub const ATTR_NAME_HISTORY: &str = "name_history";
 pub const ATTR_NAME: &str = "name";
 pub const ATTR_NO_INDEX: &str = "no-index";
+pub const ATTR_NOTIFICATION_OPT_OUT: &str = "notification_opt_out";
 pub const ATTR_NSACCOUNTLOCK: &str = "nsaccountlock";
 pub const ATTR_NSUNIQUEID: &str = "nsuniqueid";
 
 pub const ATTR_OAUTH2_ALLOW_INSECURE_CLIENT_DISABLE_PKCE: &str =
     "oauth2_allow_insecure_client_disable_pkce";
+pub const ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE: &str =
+    "oauth2_allow_insecure_refresh_token_reuse";
 pub const ATTR_OAUTH2_ALLOW_LOCALHOST_REDIRECT: &str = "oauth2_allow_localhost_redirect";
+pub const ATTR_OAUTH2_ALLOWED_GRANT_TYPES: &str = "oauth2_allowed_grant_types";
+pub const ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES: &str = "oauth2_allowed_response_types";
 pub const ATTR_OAUTH2_CONSENT_SCOPE_MAP: &str = "oauth2_consent_scope_map";
 pub const ATTR_OAUTH2_DEVICE_FLOW_ENABLE: &str = "oauth2_device_flow_enable";
 pub const ATTR_OAUTH2_JWT_LEGACY_CRYPTO_ENABLE: &str = "oauth2_jwt_legacy_crypto_enable";
+pub const ATTR_OAUTH2_JWT_SIGN_ALG: &str = "oauth2_jwt_sign_alg";
 pub const ATTR_OAUTH2_PREFER_SHORT_USERNAME: &str = "oauth2_prefer_short_username";
+pub const ATTR_OAUTH2_REDIRECT_URI_MODE: &str = "oauth2_redirect_uri_mode";
+pub const ATTR_OAUTH2_RS_ASSIGNED_MEMBER: &str = "oauth2_rs_assigned_member";
 pub const ATTR_OAUTH2_RS_BASIC_SECRET: &str = "oauth2_rs_basic_secret";
+pub const ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY: &str = "oauth2_rs_basic_secret_expiry";
+pub const ATTR_OAUTH2_RS_BASIC_SECRET_LAST_USED: &str = "oauth2_rs_basic_secret_last_used";
+pub const ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS: &str = "oauth2_rs_basic_secret_previous";
+pub const ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY: &str =
+    "oauth2_rs_basic_secret_previous_expiry";
+pub const ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED: &str =
+    "oauth2_rs_basic_secret_previous_last_used";
 pub const ATTR_OAUTH2_RS_CLAIM_MAP: &str = "oauth2_rs_claim_map";
 pub const ATTR_OAUTH2_RS_IMPLICIT_SCOPES: &str = "oauth2_rs_implicit_scopes";
 pub const ATTR_OAUTH2_RS_NAME: &str = "oauth2_rs_name";
 pub const ATTR_OAUTH2_RS_ORIGIN_LANDING: &str = "oauth2_rs_origin_landing";
 pub const ATTR_OAUTH2_RS_ORIGIN: &str = "oauth2_rs_origin";
+pub const ATTR_OAUTH2_RS_SCOPE_DESCRIPTION: &str = "oauth2_rs_scope_description";
+pub const ATTR_OAUTH2_RS_COMPUTED_CLAIM: &str = "oauth2_rs_computed_claim";
 pub const ATTR_OAUTH2_RS_SCOPE_MAP: &str = "oauth2_rs_scope_map";
 pub const ATTR_OAUTH2_RS_SUP_SCOPE_MAP: &str = "oauth2_rs_sup_scope_map";
 pub const ATTR_OAUTH2_RS_TOKEN_KEY: &str = "oauth2_rs_token_key";
 pub const ATTR_OAUTH2_SESSION: &str = "oauth2_session";
 pub const ATTR_OAUTH2_STRICT_REDIRECT_URI: &str = "oauth2_strict_redirect_uri";
+pub const ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE: &str = "oauth2_token_exchange_audience";
 pub const ATTR_OBJECTCLASS: &str = "objectclass";
+pub const ATTR_OIDC_UPSTREAM_AUTHORISATION_ENDPOINT: &str = "oidc_upstream_authorisation_endpoint";
+pub const ATTR_OIDC_UPSTREAM_CLIENT_ID: &str = "oidc_upstream_client_id";
+pub const ATTR_OIDC_UPSTREAM_CLIENT_SECRET: &str = "oidc_upstream_client_secret";
+pub const ATTR_OIDC_UPSTREAM_ISSUER: &str = "oidc_upstream_issuer";
+pub const ATTR_OIDC_UPSTREAM_JWKS_URI: &str = "oidc_upstream_jwks_uri";
+pub const ATTR_OIDC_UPSTREAM_TOKEN_ENDPOINT: &str = "oidc_upstream_token_endpoint";
 pub const ATTR_OTHER_NO_INDEX: &str = "other-no-index";
+pub const ATTR_PARENT_GROUP: &str = "parent_group";
 pub const ATTR_PASSKEYS: &str = "passkeys";
+pub const ATTR_PASSWORD_BREACH_CHECK: &str = "password_breach_check";
+pub const ATTR_PASSWORD_HISTORY: &str = "password_history";
+pub const ATTR_PASSWORD_LAST_CHANGED: &str = "password_last_changed";
 pub const ATTR_PASSWORD_IMPORT: &str = "password_import";
 pub const ATTR_PATCH_LEVEL: &str = "patch_level";
+pub const ATTR_PHONE: &str = "phone";
+pub const ATTR_POSIX_DEFAULT_SHELL: &str = "posix_default_shell";
+pub const ATTR_POSIX_GECOS_FORMAT: &str = "posix_gecos_format";
+pub const ATTR_POSIX_HOME_QUOTA: &str = "posix_home_quota";
+pub const ATTR_POSIX_HOME_SKELETON: &str = "posix_home_skeleton";
+pub const ATTR_POSIX_HOME_TEMPLATE: &str = "posix_home_template";
 pub const ATTR_PHANTOM: &str = "phantom";
 pub const ATTR_PRIMARY_CREDENTIAL: &str = "primary_credential";
 pub const ATTR_TOTP_IMPORT: &str = "totp_import";
@@ -183,15 +277,22 @@ pub const ATTR_RECYCLEDDIRECTMEMBEROF: &str = "recycled_directmemberof";
 pub const ATTR_REFERS: &str = "refers";
 pub const ATTR_REPLICATED: &str = "replicated";
 pub const ATTR_RS256_PRIVATE_KEY_DER: &str = "rs256_private_key_der";
+pub const ATTR_SAML2_ACS_URL: &str = "saml2_acs_url";
+pub const ATTR_SAML2_ENTITY_ID: &str = "saml2_entity_id";
+pub const ATTR_SAML2_GROUP_MAP: &str = "saml2_group_map";
 pub const ATTR_SCIM_SCHEMAS: &str = "schemas";
 pub const ATTR_SCOPE: &str = "scope";
 pub const ATTR_SELF: &str = "self";
 pub const ATTR_SOURCE_UUID: &str = "source_uuid";
 pub const ATTR_SPN: &str = "spn";
+pub const ATTR_SPONSOR: &str = "sponsor";
 pub const ATTR_SUDOHOST: &str = "sudohost";
 pub const ATTR_SUPPLEMENTS: &str = "supplements";
 pub const ATTR_LDAP_SSHPUBLICKEY: &str = "sshpublickey";
 pub const ATTR_SSH_PUBLICKEY: &str = "ssh_publickey";
+pub const ATTR_SSH_PUBLICKEY_EXPIRY: &str = "ssh_publickey_expiry";
+pub const ATTR_SSH_KEY_ALLOWED_TYPE: &str = "ssh_key_allowed_type";
+pub const ATTR_SSH_KEY_RSA_MINIMUM_BITS: &str = "ssh_key_rsa_minimum_bits";
 pub const ATTR_SYNC_ALLOWED: &str = "sync_allowed";
 pub const ATTR_SYNC_CLASS: &str = "sync_class";
 pub const ATTR_SYNC_COOKIE: &str = "sync_cookie";
@@ -201,12 +302,15 @@ pub const ATTR_SYNC_EXTERNAL_UUID: &str = "sync_external_uuid";
 pub const ATTR_SYNC_PARENT_UUID: &str = "sync_parent_uuid";
 pub const ATTR_SYNC_TOKEN_SESSION: &str = "sync_token_session";
 pub const ATTR_SYNC_YIELD_AUTHORITY: &str = "sync_yield_authority";
+pub const ATTR_SYNC_CONFLICT: &str = "sync_conflict";
 pub const ATTR_SYNTAX: &str = "syntax";
 pub const ATTR_SYSTEMEXCLUDES: &str = "systemexcludes";
 pub const ATTR_SYSTEMMAY: &str = "systemmay";
 pub const ATTR_SYSTEMMUST: &str = "systemmust";
 pub const ATTR_SYSTEMSUPPLEMENTS: &str = "systemsupplements";
+pub const ATTR_TELEPHONE_NUMBER: &str = "telephonenumber";
 pub const ATTR_TERM: &str = "term";
+pub const ATTR_UI_THEME: &str = "ui_theme";
 pub const ATTR_UID: &str = "uid";
 pub const ATTR_UIDNUMBER: &str = "uidnumber";
 pub const ATTR_UNIQUE: &str = "unique";
@@ -218,12 +322,23 @@ pub const ATTR_USERPASSWORD: &str = "userpassword";
 pub const ATTR_UUID: &str = "uuid";
 pub const ATTR_VERSION: &str = "version";
 pub const ATTR_WEBAUTHN_ATTESTATION_CA_LIST: &str = "webauthn_attestation_ca_list";
+pub const ATTR_WEBHOOK_FILTER: &str = "webhook_filter";
+pub const ATTR_WEBHOOK_SECRET: &str = "webhook_secret";
+pub const ATTR_WEBHOOK_URL: &str = "webhook_url";
+pub const ATTR_WHITE_PAGES_RATE_LIMIT: &str = "white_pages_rate_limit";
+pub const ATTR_WHITE_PAGES_TRUSTED_NETWORK: &str = "white_pages_trusted_network";
 pub const ATTR_ALLOW_PRIMARY_CRED_FALLBACK: &str = "allow_primary_cred_fallback";
+pub const ATTR_ALLOW_API_TOKENS: &str = "allow_api_tokens";
+pub const ATTR_ALLOW_EMAIL_LINK: &str = "allow_email_link";
+pub const ATTR_ALLOW_UNIX_PASSWORD: &str = "allow_unix_password";
 
 pub const SUB_ATTR_PRIMARY: &str = "primary";
 
 pub const OAUTH2_SCOPE_EMAIL: &str = ATTR_EMAIL;
 pub const OAUTH2_SCOPE_GROUPS: &str = "groups";
+pub const OAUTH2_SCOPE_GROUPS_NAME: &str = "groups_name";
+pub const OAUTH2_SCOPE_GROUPS_SPN: &str = "groups_spn";
+pub const OAUTH2_SCOPE_GROUPS_UUID: &str = "groups_uuid";
 pub const OAUTH2_SCOPE_SSH_PUBLICKEYS: &str = "ssh_publickeys";
 pub const OAUTH2_SCOPE_OPENID: &str = "openid";
 pub const OAUTH2_SCOPE_READ: &str = "read";
@@ -263,6 +378,14 @@ pub const KOPID: &str = "X-KANIDM-OPID";
 /// HTTP Header containing the Kanidm server version
 pub const KVERSION: &str = "X-KANIDM-VERSION";
 
+/// HTTP Header containing a client chosen key that makes retries of a write request safe
+pub const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+/// HTTP Header set when a response is the replayed outcome of an earlier request
+pub const IDEMPOTENT_REPLAYED: &str = "Idempotent-Replayed";
+
+/// HTTP Header containing a DPoP proof of possession, see RFC 9449
+pub const DPOP: &str = "DPoP";
+
 /// X-Forwarded-For header
 pub const X_FORWARDED_FOR: &str = "x-forwarded-for";
 
@@ -271,6 +394,7 @@ pub const OAUTH2_DEVICE_CODE_SESSION: &str = "oauth2_device_code_session";
 pub const OAUTH2_RESOURCE_SERVER: &str = "oauth2_resource_server";
 pub const OAUTH2_RESOURCE_SERVER_BASIC: &str = "oauth2_resource_server_basic";
 pub const OAUTH2_RESOURCE_SERVER_PUBLIC: &str = "oauth2_resource_server_public";
+pub const OAUTH2_RESOURCE_SERVER_NATIVE: &str = "oauth2_resource_server_native";
 
 // Access Control
 pub const ACCESS_CONTROL_CREATE: &str = "access_control_create";
@@ -280,29 +404,39 @@ pub const ACCESS_CONTROL_PROFILE: &str = "access_control_profile";
 pub const ACCESS_CONTROL_RECEIVER_ENTRY_MANAGER: &str = "access_control_receiver_entry_manager";
 pub const ACCESS_CONTROL_RECEIVER_GROUP: &str = "access_control_receiver_group";
 pub const ACCESS_CONTROL_SEARCH: &str = "access_control_search";
+pub const ACCESS_CONTROL_TARGET_GROUP: &str = "access_control_target_group";
 pub const ACCESS_CONTROL_TARGET_SCOPE: &str = "access_control_target_scope";
 
 /// Entryclass
 pub const ENTRYCLASS_BUILTIN: &str = "builtin";
 pub const ENTRYCLASS_ACCOUNT: &str = "account";
 pub const ENTRYCLASS_ACCOUNT_POLICY: &str = "account_policy";
+pub const ENTRYCLASS_ANNOUNCEMENT: &str = "announcement";
 pub const ENTRYCLASS_APPLICATION: &str = "application";
 pub const ENTRYCLASS_ATTRIBUTE_TYPE: &str = "attributetype";
 pub const ENTRYCLASS_CLASS: &str = "class";
 pub const ENTRYCLASS_CLASS_TYPE: &str = "classtype";
 pub const ENTRYCLASS_CLIENT_CERTIFICATE: &str = "client_certificate";
 pub const ENTRYCLASS_CONFLICT: &str = "conflict";
+pub const ENTRYCLASS_CONTRACTOR: &str = "contractor";
 pub const ENTRYCLASS_DOMAIN_INFO: &str = "domain_info";
 pub const ENTRYCLASS_DYN_GROUP: &str = "dyngroup";
 pub const ENTRYCLASS_EXTENSIBLE_OBJECT: &str = "extensibleobject";
 pub const ENTRYCLASS_GROUP: &str = "group";
+pub const ENTRYCLASS_GROUP_MEMBERSHIP_REQUEST: &str = "group_membership_request";
+pub const ENTRYCLASS_HBAC_RULE: &str = "hbac_rule";
+pub const ENTRYCLASS_HOST: &str = "host";
+pub const ENTRYCLASS_HOST_GROUP: &str = "host_group";
 pub const ENTRYCLASS_MEMBER_OF: &str = "memberof";
+pub const ENTRYCLASS_MIGRATION_RECORD: &str = "migration_record";
 pub const ENTRYCLASS_OBJECT: &str = "object";
+pub const ENTRYCLASS_OIDC_UPSTREAM: &str = "oidc_upstream";
 pub const ENTRYCLASS_ORG_PERSON: &str = "orgperson";
 pub const ENTRYCLASS_PERSON: &str = "person";
 pub const ENTRYCLASS_POSIX_ACCOUNT: &str = "posixaccount";
 pub const ENTRYCLASS_POSIX_GROUP: &str = "posixgroup";
 pub const ENTRYCLASS_RECYCLED: &str = "recycled";
+pub const ENTRYCLASS_SAML2_SERVICE_PROVIDER: &str = "saml2_service_provider";
 pub const ENTRYCLASS_SERVICE: &str = "service";
 pub const ENTRYCLASS_SERVICE_ACCOUNT: &str = "service_account";
 pub const ENTRYCLASS_SYNC_ACCOUNT: &str = "sync_account";
@@ -312,9 +446,15 @@ pub const ENTRYCLASS_SYSTEM_CONFIG: &str = "system_config";
 pub const ENTRYCLASS_SYSTEM_INFO: &str = "system_info";
 pub const ENTRYCLASS_TOMBSTONE: &str = "tombstone";
 pub const ENTRYCLASS_USER: &str = "user";
+pub const ENTRYCLASS_WEBHOOK: &str = "webhook";
 pub const ENTRYCLASS_KEY_PROVIDER: &str = "key_provider";
 pub const ENTRYCLASS_KEY_PROVIDER_INTERNAL: &str = "key_provider_internal";
 pub const ENTRYCLASS_KEY_OBJECT: &str = "key_object";
 pub const ENTRYCLASS_KEY_OBJECT_JWT_ES256: &str = "key_object_jwt_es256";
+pub const ENTRYCLASS_KEY_OBJECT_JWT_RS256: &str = "key_object_jwt_rs256";
 pub const ENTRYCLASS_KEY_OBJECT_JWE_A128GCM: &str = "key_object_jwe_a128gcm";
+pub const ENTRYCLASS_KEY_OBJECT_REPL_TRUST_ANCHOR: &str = "key_object_repl_trust_anchor";
+pub const ENTRYCLASS_KEY_OBJECT_SSH_CA: &str = "key_object_ssh_ca";
+pub const ENTRYCLASS_KEY_OBJECT_SAML2_SIGNING: &str = "key_object_saml2_signing";
+pub const ENTRYCLASS_KEY_OBJECT_KERBEROS: &str = "key_object_kerberos";
 pub const ENTRYCLASS_KEY_OBJECT_INTERNAL: &str = "key_object_internal";
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+
+/// The lockout that is applied to a credential after repeated failed authentication
+/// attempts. The window and duration are in seconds.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct AccountPolicyLockout {
+    pub threshold: u32,
+    pub window: u32,
+    pub duration: u32,
+}
+
+/// Networks that authentication is trusted from. Outside of these networks the credential
+/// type minimum applies.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct AccountPolicyNetwork {
+    pub trusted_networks: Vec<String>,
+    pub credential_type_minimum: String,
+}
+
+/// The account policy that applies to an account, merged from the policies of all the groups
+/// that the account is a member of. Durations are in seconds.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct EffectiveAccountPolicy {
+    pub credential_type_minimum: String,
+    pub password_minimum_length: u32,
+    pub password_history_count: u32,
+    /// If passkeys must be attested by one of a set of trusted authenticators.
+    pub webauthn_attestation_required: bool,
+    pub allow_primary_credential_fallback: Option<bool>,
+    pub allow_unix_password: bool,
+    pub allow_api_tokens: bool,
+    /// If the account may authenticate with a link sent to their mail address.
+    pub allow_email_link: bool,
+    /// If new passwords are rejected when they are found in the dataset of breached passwords.
+    pub password_breach_check: bool,
+    /// The number of seconds after which a password must be changed.
+    pub password_maximum_age: Option<u32>,
+    pub authsession_expiry: u32,
+    /// The number of seconds a session may go unused before it expires.
+    pub authsession_idle_timeout: Option<u32>,
+    pub privilege_expiry: u32,
+    pub limit_search_max_results: Option<u64>,
+    pub limit_search_max_filter_test: Option<u64>,
+    pub login_host_tags: Option<Vec<String>>,
+    pub ssh_key_allowed_types: Option<Vec<String>>,
+    pub ssh_key_rsa_minimum_bits: u32,
+    pub posix_default_shell: Option<String>,
+    pub posix_home_template: Option<String>,
+    pub posix_gecos_format: Option<String>,
+    pub posix_home_skeleton: Option<String>,
+    pub network_policies: Vec<AccountPolicyNetwork>,
+    pub lockout: Option<AccountPolicyLockout>,
+    pub account_lifetime: Option<u64>,
+    pub account_archive_delay: Option<u64>,
+}
+
+impl fmt::Display for EffectiveAccountPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn opt<T: fmt::Display>(value: &Option<T>) -> String {
+            value
+                .as_ref()
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "-".to_string())
+        }
+
+        fn list(value: &Option<Vec<String>>) -> String {
+            value
+                .as_ref()
+                .map(|v| v.join(", "))
+                .unwrap_or_else(|| "-".to_string())
+        }
+
+        writeln!(
+            f,
+            "credential type minimum: {}",
+            self.credential_type_minimum
+        )?;
+        writeln!(
+            f,
+            "password minimum length: {}",
+            self.password_minimum_length
+        )?;
+        writeln!(f, "password history count: {}", self.password_history_count)?;
+        writeln!(
+            f,
+            "webauthn attestation required: {}",
+            self.webauthn_attestation_required
+        )?;
+        writeln!(
+            f,
+            "allow primary credential fallback: {}",
+            opt(&self.allow_primary_credential_fallback)
+        )?;
+        writeln!(f, "allow unix password: {}", self.allow_unix_password)?;
+        writeln!(f, "allow api tokens: {}", self.allow_api_tokens)?;
+        writeln!(f, "allow email link: {}", self.allow_email_link)?;
+        writeln!(f, "password breach check: {}", self.password_breach_check)?;
+        writeln!(
+            f,
+            "password maximum age: {}",
+            opt(&self.password_maximum_age)
+        )?;
+        writeln!(f, "auth session expiry: {}", self.authsession_expiry)?;
+        writeln!(
+            f,
+            "auth session idle timeout: {}",
+            opt(&self.authsession_idle_timeout)
+        )?;
+        writeln!(f, "privilege expiry: {}", self.privilege_expiry)?;
+        writeln!(
+            f,
+            "limit search max results: {}",
+            opt(&self.limit_search_max_results)
+        )?;
+        writeln!(
+            f,
+            "limit search max filter test: {}",
+            opt(&self.limit_search_max_filter_test)
+        )?;
+        writeln!(f, "login host tags: {}", list(&self.login_host_tags))?;
+        writeln!(
+            f,
+            "ssh key allowed types: {}",
+            list(&self.ssh_key_allowed_types)
+        )?;
+        writeln!(
+            f,
+            "ssh key rsa minimum bits: {}",
+            self.ssh_key_rsa_minimum_bits
+        )?;
+        writeln!(f, "posix default shell: {}", opt(&self.posix_default_shell))?;
+        writeln!(f, "posix home template: {}", opt(&self.posix_home_template))?;
+        writeln!(f, "posix gecos format: {}", opt(&self.posix_gecos_format))?;
+        writeln!(f, "posix home skeleton: {}", opt(&self.posix_home_skeleton))?;
+        for network in &self.network_policies {
+            writeln!(
+                f,
+                "network policy: {} outside of {}",
+                network.credential_type_minimum,
+                network.trusted_networks.join(", ")
+            )?;
+        }
+        match &self.lockout {
+            Some(lockout) => writeln!(
+                f,
+                "lockout: {} failures in {} seconds locks for {} seconds",
+                lockout.threshold, lockout.window, lockout.duration
+            )?,
+            None => writeln!(f, "lockout: -")?,
+        }
+        writeln!(f, "account lifetime: {}", opt(&self.account_lifetime))?;
+        writeln!(
+            f,
+            "account archive delay: {}",
+            opt(&self.account_archive_delay)
+        )
+    }
+}
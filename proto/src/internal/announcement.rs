@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use time::OffsetDateTime;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// How prominently an announcement is shown.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnouncementSeverity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for AnnouncementSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnnouncementSeverity::Info => write!(f, "info"),
+            AnnouncementSeverity::Warning => write!(f, "warning"),
+            AnnouncementSeverity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+impl FromStr for AnnouncementSeverity {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "info" => Ok(AnnouncementSeverity::Info),
+            "warning" => Ok(AnnouncementSeverity::Warning),
+            "critical" => Ok(AnnouncementSeverity::Critical),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A message from the administrators of the instance, such as a maintenance window or a
+/// phishing warning, that is shown between its start and end times.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Announcement {
+    pub uuid: Uuid,
+    pub message: String,
+    pub severity: AnnouncementSeverity,
+    /// If not set, the announcement is shown from when it is created.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub start: Option<OffsetDateTime>,
+    /// If not set, the announcement is shown until it is deleted.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub end: Option<OffsetDateTime>,
+    /// If the announcement is also shown by command line tools.
+    pub cli: bool,
+}
+
+impl Announcement {
+    /// If the announcement should be shown at this time.
+    pub fn is_active(&self, time: OffsetDateTime) -> bool {
+        self.start.map_or(true, |start| start <= time) && self.end.map_or(true, |end| time < end)
+    }
+}
+
+impl fmt::Display for Announcement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.severity, self.message)
+    }
+}
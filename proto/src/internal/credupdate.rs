@@ -66,6 +66,52 @@ pub struct CUIntentToken {
     pub token: String,
     #[serde(with = "time::serde::timestamp")]
     pub expiry_time: time::OffsetDateTime,
+    /// The number of times this token may be used to update credentials.
+    #[serde(default = "cu_intent_default_max_uses")]
+    pub max_uses: u32,
+    /// The address the token was mailed to, if it was delivered directly to the person.
+    #[serde(default)]
+    pub mailed_to: Option<String>,
+}
+
+fn cu_intent_default_max_uses() -> u32 {
+    1
+}
+
+/// Request to create a credential reset token for an account.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct CUIntentTokenRequest {
+    /// How long the token is valid for, in seconds.
+    pub ttl: Option<u64>,
+    /// How many times the token may be used to update credentials.
+    pub max_uses: Option<u32>,
+    /// Mail the token to the primary mail address of the person.
+    #[serde(default)]
+    pub send_mail: bool,
+}
+
+/// The state of a credential reset token that was issued for an account.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CUIntentTokenState {
+    Valid {
+        uses_remaining: u32,
+    },
+    /// The token is being used in a credential update session.
+    InProgress {
+        uses_remaining: u32,
+    },
+    Consumed,
+}
+
+/// A credential reset token that was issued for an account. Tokens are secrets, so they are
+/// identified by a reference that can be used to revoke them.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CUIntentTokenInfo {
+    pub token_ref: String,
+    #[serde(with = "time::serde::timestamp")]
+    pub expiry_time: time::OffsetDateTime,
+    pub state: CUIntentTokenState,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
@@ -161,6 +207,7 @@ pub enum CURegWarning {
     AttestedResidentKeyRequired,
     Unsatisfiable,
     WebauthnAttestationUnsatisfiable,
+    WebauthnAttestationNotTrusted,
     WebauthnUserVerificationRequired,
 }
 
@@ -188,11 +235,21 @@ pub struct CUStatus {
 
     pub sshkeys: BTreeMap<String, SshPublicKey>,
     pub sshkeys_state: CUCredState,
+
+    /// If this session was started from a reset token, when that token expires.
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub intent_expiry_time: Option<time::OffsetDateTime>,
+    /// If this session was started from a reset token, how many uses it has left.
+    #[serde(default)]
+    pub intent_uses_remaining: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct CredentialStatus {
     pub creds: Vec<CredentialDetail>,
+    /// When each factor of these credentials, and of any passkeys, was last used.
+    #[serde(default)]
+    pub usage: Vec<CredentialUsageDetail>,
 }
 
 impl fmt::Display for CredentialStatus {
@@ -201,10 +258,69 @@ impl fmt::Display for CredentialStatus {
             writeln!(f, "---")?;
             cred.fmt(f)?;
         }
+        if !self.usage.is_empty() {
+            writeln!(f, "---")?;
+            writeln!(f, "usage:")?;
+            for usage in &self.usage {
+                usage.fmt(f)?;
+            }
+        }
         writeln!(f, "---")
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CredentialUsageDetail {
+    /// The credential or passkey that was used.
+    pub uuid: Uuid,
+    /// The factor of the credential that was used, such as a password or a named totp.
+    pub factor: String,
+    #[serde(with = "time::serde::timestamp")]
+    pub last_used: time::OffsetDateTime,
+    pub use_count: u64,
+    /// Where the credential was last used from, if known.
+    pub source: Option<String>,
+}
+
+impl fmt::Display for CredentialUsageDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            " * {}: last used {} ({} uses)",
+            self.factor, self.last_used, self.use_count
+        )?;
+        match &self.source {
+            Some(source) => writeln!(f, " from {}", source),
+            None => writeln!(f),
+        }
+    }
+}
+
+/// The softlock of an account's primary credential. Softlocks are held in memory, so this
+/// only reflects the server that answered the request.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct CredentialSoftLockStatus {
+    /// The number of failed authentication attempts in the current cycle.
+    pub failures: u64,
+    /// If authentication is currently denied, the time at which it is next allowed.
+    #[serde(with = "time::serde::timestamp::option")]
+    pub unlock_at: Option<time::OffsetDateTime>,
+    /// If the credential is locked until the end of the current cycle, rather than only
+    /// delayed between attempts.
+    pub locked_out: bool,
+}
+
+impl fmt::Display for CredentialSoftLockStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "failures: {}", self.failures)?;
+        match (&self.unlock_at, self.locked_out) {
+            (Some(unlock_at), true) => writeln!(f, "locked until: {}", unlock_at),
+            (Some(unlock_at), false) => writeln!(f, "delayed until: {}", unlock_at),
+            (None, _) => writeln!(f, "not locked"),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
 pub enum CredentialDetailType {
     Password,
@@ -289,6 +405,18 @@ pub struct BackupCodesView {
     pub backup_codes: Vec<String>,
 }
 
+/// The verdict on a candidate password for the account in a credential update session. The
+/// password is not set by checking it.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CUPasswordCheck {
+    /// If the password would be accepted.
+    pub acceptable: bool,
+    /// The minimum length of a password for this account.
+    pub min_length: u32,
+    /// Why the password would be rejected. This is empty when it is acceptable.
+    pub feedback: Vec<PasswordFeedback>,
+}
+
 #[derive(Serialize, Deserialize, Debug, ToSchema, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum PasswordFeedback {
@@ -325,6 +453,8 @@ pub enum PasswordFeedback {
     TooShort(u32),
     BadListed,
     DontReusePasswords,
+    DenyListed(String),
+    Breached,
 }
 
 /// Human-readable PasswordFeedback result.
@@ -355,6 +485,10 @@ impl fmt::Display for PasswordFeedback {
                 f,
                 "This password has been compromised or otherwise blocked and can not be used."
             ),
+            PasswordFeedback::Breached => write!(
+                f,
+                "This password has appeared in a data breach and can not be used."
+            ),
             PasswordFeedback::CapitalizationDoesntHelpVeryMuch => {
                 write!(f, "Capitalization doesn't help very much.")
             }
@@ -364,6 +498,11 @@ impl fmt::Display for PasswordFeedback {
             PasswordFeedback::DatesAreOftenEasyToGuess => {
                 write!(f, "Dates are often easy to guess.")
             }
+            PasswordFeedback::DenyListed(term) => write!(
+                f,
+                "Passwords may not contain '{}' or a variation of it.",
+                term
+            ),
             PasswordFeedback::DontReusePasswords => {
                 write!(
                     f,
@@ -1,6 +1,7 @@
 use std::fmt::{Display, Formatter};
 
 use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -53,6 +54,7 @@ pub enum ConsistencyError {
     DuplicateUniqueAttribute,
     InvalidSpn(u64),
     SqliteIntegrityFailure,
+    PostgresIntegrityFailure,
     BackendAllIdsSync,
     BackendIndexSync,
     ChangelogDesynchronised(u64),
@@ -109,6 +111,7 @@ pub enum OperationError {
     ModifyAssertionFailed,
     BackendEngine,
     SqliteError, //(RusqliteError)
+    PostgresError,
     FsError,
     SerdeJsonError,
     SerdeCborError,
@@ -132,6 +135,7 @@ pub enum OperationError {
     ReplDomainLevelUnsatisfiable,
     ReplDomainUuidMismatch,
     ReplServerUuidSplitDataState,
+    ReplClockSkewExceedsTolerance,
     TransactionAlreadyCommitted,
     CannotStartMFADuringOngoingMFASession,
     /// when you ask for a gid that overlaps a system reserved range
@@ -149,11 +153,18 @@ pub enum OperationError {
     AU0005DelayedProcessFailure,
     AU0006CredentialMayNotReauthenticate,
     AU0007UserAuthTokenInvalid,
+    AU0008FederatedRequestInvalid,
+    AU0009FederatedExchangeFailed,
 
     // Kanidm Generic Errors
     KG001TaskTimeout,
     KG002TaskCommFailure,
     KG003CacheClearFailed,
+    KG004MailNotConfigured,
+    KG005MailDeliveryFailed,
+    KG006WhitePagesRateLimited,
+    KG007AcmeOrderFailed,
+    KG008MaintenanceModeReadOnly,
 
     // Credential Update Errors
     CU0001WebauthnAttestationNotTrusted,
@@ -167,6 +178,10 @@ pub enum OperationError {
     CU0005IntentTokenConflict,
     // The intent token was invalidated before we could commit.
     CU0006IntentTokenInvalidated,
+    // Too many candidate passwords have been checked in this session recently.
+    CU0007PasswordCheckRateLimited,
+    // A credential reset token was to be mailed, but the account has no mail address.
+    CU0008IntentTokenNoMail,
 
     // ValueSet errors
     VS0001IncomingReplSshPublicKey,
@@ -185,6 +200,13 @@ pub enum OperationError {
     DB0002MismatchedRestoreVersion,
     DB0003FilterResolveCacheBuild,
     DB0004DatabaseTooOld,
+    DB0005MismatchedRestoreDomain,
+    DB0006RestoreEntryNotFound,
+    DB0007RestoreEntryTombstoned,
+    DB0008DataEncryptionKeyRequired,
+    DB0009DataEncryptionKeyInvalid,
+    DB0010EncryptedValueInvalid,
+    DB0011StorageOptionUnsupported,
 
     // SCIM
     SC0001IncomingSshPublicKey,
@@ -269,14 +291,69 @@ pub enum OperationError {
     KP0042KeyObjectNoActiveEncryptionKeys,
     KP0043KeyObjectJweA128GCMEncryption,
     KP0044KeyObjectJwsPublicJwk,
+    KP0045KeyObjectSelfTestJwsMismatch,
+    KP0046KeyObjectSelfTestJweMismatch,
+    KP0047KeyObjectReplTrustAnchorGeneration,
+    KP0048KeyObjectReplTrustAnchorInvalid,
+    KP0049KeyObjectNoActiveReplTrustAnchor,
+    KP0050KeyObjectReplIdentityIssue,
+    KP0051KeyProviderNoSuchKey,
+    KP0052KeyObjectSelfTestReplIdentityInvalid,
+    KP0053KeyObjectSshCaGeneration,
+    KP0054KeyObjectSshCaInvalid,
+    KP0055KeyObjectNoActiveSshCa,
+    KP0056KeyObjectSshCertificateIssue,
+    KP0057KeyObjectSelfTestSshCertificateInvalid,
+    KP0058KeyObjectSaml2SigningGeneration,
+    KP0059KeyObjectSaml2SigningInvalid,
+    KP0060KeyObjectNoActiveSaml2Signing,
+    KP0061KeyObjectSelfTestSaml2SignatureInvalid,
+    KP0062KeyObjectKerberosInvalid,
+    KP0063KeyObjectNoActiveKerberosKey,
+    KP0064KeyObjectKerberosTicketIssue,
+    KP0065KeyObjectSelfTestKerberosKeyInvalid,
+    KP0066KeyObjectJwsRs256Generation,
+    KP0067KeyObjectJwsRs256DerInvalid,
+    KP0068KeyObjectJwsRs256Signature,
+    KP0069KeyObjectJwsRs256PublicJwkInvalid,
+    KP0070KeyObjectNoJwsSigningKey,
+
+    // OAuth2
+    OA0001RedirectUriNotRegistered,
+    OA0002RedirectUriOriginNotRegistered,
+    OA0003RedirectUriHttpsRequired,
+    OA0004RedirectUriLoopbackNotRegistered,
+    OA0005RedirectUriPrivateUseSchemeInvalid,
+    OA0006RedirectUriPrivateUseSchemeNotRegistered,
+
+    // SAML2
+    SA0001AuthnRequestInvalid,
+    SA0002AcsUrlNotRegistered,
+    SA0003AuthnRequestIssuerMismatch,
+
+    // Groups
+    GR0001GroupMembershipNotRequestable,
+    GR0002GroupMembershipRequestExists,
+    GR0003GroupMembershipRequestDecided,
 
     // Plugins
     PL0001GidOverlapsSystemRange,
+    PL0002ContractorSponsorInvalid,
+    PL0003SshPublicKeyPolicyDenied,
+    PL0004GroupParentInvalid,
 
     // Web UI
     UI0001ChallengeSerialisation,
     UI0002InvalidState,
     UI0003InvalidOauth2Resume,
+    UI0004InvalidSaml2Resume,
+
+    // Http
+    HT0001IdempotencyKeyInProgress,
+    HT0002IdempotencyKeyReused,
+    HT0003IdempotencyRequestTooLarge,
+    HT0004IdempotencyKeyInvalid,
+    HT0005EntryVersionMismatch,
 
     // Unixd Things
     KU001InitWhileSessionActive,
@@ -285,6 +362,8 @@ pub enum OperationError {
     KU004PamInitFailed,
     KU005ErrorCheckingAccount,
     KU006OnlyRootAllowed,
+    KU007MachineEnrollmentFailed,
+    KU008KerberosKeytabFailed,
 }
 
 impl PartialEq for OperationError {
@@ -353,6 +432,7 @@ impl OperationError {
             Self::ModifyAssertionFailed => None,
             Self::BackendEngine => None,
             Self::SqliteError => None,
+            Self::PostgresError => None,
             Self::FsError => None,
             Self::SerdeJsonError => None,
             Self::SerdeCborError => None,
@@ -376,6 +456,7 @@ impl OperationError {
             Self::ReplDomainLevelUnsatisfiable => None,
             Self::ReplDomainUuidMismatch => None,
             Self::ReplServerUuidSplitDataState => None,
+            Self::ReplClockSkewExceedsTolerance => Some("The incoming changes are further ahead of the local clock than the configured tolerance allows.".into()),
             Self::TransactionAlreadyCommitted => None,
             Self::ValueDenyName => None,
             Self::DatabaseLockAcquisitionTimeout => Some("Unable to acquire a database lock - the current server may be too busy. Try again later.".into()),
@@ -387,6 +468,8 @@ impl OperationError {
     Self::AU0005DelayedProcessFailure => Some("Delaying processing failure, unable to proceed".into()),
     Self::AU0006CredentialMayNotReauthenticate => Some("Credential may not reauthenticate".into()),
     Self::AU0007UserAuthTokenInvalid => Some("User auth token was unable to be generated".into()),
+    Self::AU0008FederatedRequestInvalid => Some("The login with the upstream identity provider does not belong to this authentication session".into()),
+    Self::AU0009FederatedExchangeFailed => Some("Unable to retrieve the identity from the upstream identity provider".into()),
 
             Self::CU0001WebauthnAttestationNotTrusted => None,
             Self::CU0002WebauthnRegistrationError => None,
@@ -395,14 +478,28 @@ impl OperationError {
             Self::CU0004SessionInconsistent => Some("The session is unable to be committed due to unresolved warnings.".into()),
             Self::CU0005IntentTokenConflict => Some("The intent token used to create this session has been reused in another browser/tab and may not proceed.".into()),
             Self::CU0006IntentTokenInvalidated => Some("The intent token has been invalidated/revoked before the commit could be accepted. Has it been used in another browser or tab?".into()),
+            Self::CU0007PasswordCheckRateLimited => Some("Too many passwords have been checked recently, wait before checking another.".into()),
+            Self::CU0008IntentTokenNoMail => Some("The account has no mail address that the credential reset token can be sent to.".into()),
 
             Self::DB0001MismatchedRestoreVersion => None,
             Self::DB0002MismatchedRestoreVersion => None,
             Self::DB0003FilterResolveCacheBuild => None,
             Self::DB0004DatabaseTooOld => Some("The database is too old to be migrated.".into()),
+            Self::DB0005MismatchedRestoreDomain => Some("The backups provided for a point in time restore do not belong to the same domain.".into()),
+            Self::DB0006RestoreEntryNotFound => Some("The requested entry was not found in the backup.".into()),
+            Self::DB0007RestoreEntryTombstoned => Some("The requested entry has been tombstoned and can not be restored.".into()),
+            Self::DB0008DataEncryptionKeyRequired => Some("The database contains encrypted values, but no data encryption key is configured.".into()),
+            Self::DB0009DataEncryptionKeyInvalid => Some("The data encryption keys of the database could not be unwrapped with the configured key.".into()),
+            Self::DB0010EncryptedValueInvalid => Some("An encrypted value in the database could not be decrypted.".into()),
+            Self::DB0011StorageOptionUnsupported => Some("The requested operation is not supported by the configured database type.".into()),
             Self::KG001TaskTimeout => Some("Task timed out".into()),
             Self::KG002TaskCommFailure => Some("Inter-Task communication failure".into()),
             Self::KG003CacheClearFailed => Some("Failed to clear cache".into()),
+            Self::KG004MailNotConfigured => Some("Mail delivery is not configured on this server".into()),
+            Self::KG005MailDeliveryFailed => Some("Failed to deliver mail to the relay".into()),
+            Self::KG006WhitePagesRateLimited => Some("Too many anonymous directory searches have been made from this address, wait before searching again.".into()),
+            Self::KG007AcmeOrderFailed => Some("Failed to obtain a certificate from the ACME certificate authority".into()),
+            Self::KG008MaintenanceModeReadOnly => Some("The server is in maintenance mode and is not accepting changes. Reads and authentication are still available.".into()),
             Self::KP0001KeyProviderNotLoaded => None,
             Self::KP0002KeyProviderInvalidClass => None,
             Self::KP0003KeyProviderInvalidType => None,
@@ -448,12 +545,40 @@ impl OperationError {
             Self::KP0042KeyObjectNoActiveEncryptionKeys => None,
             Self::KP0043KeyObjectJweA128GCMEncryption => None,
             Self::KP0044KeyObjectJwsPublicJwk => None,
+            Self::KP0045KeyObjectSelfTestJwsMismatch => Some("The signed self test payload did not match after verification.".into()),
+            Self::KP0046KeyObjectSelfTestJweMismatch => Some("The encrypted self test payload did not match after decryption.".into()),
+            Self::KP0047KeyObjectReplTrustAnchorGeneration => None,
+            Self::KP0048KeyObjectReplTrustAnchorInvalid => None,
+            Self::KP0049KeyObjectNoActiveReplTrustAnchor => None,
+            Self::KP0050KeyObjectReplIdentityIssue => None,
+            Self::KP0051KeyProviderNoSuchKey => None,
+            Self::KP0052KeyObjectSelfTestReplIdentityInvalid => Some("The replication identity issued by the trust anchor did not verify.".into()),
+            Self::KP0053KeyObjectSshCaGeneration => None,
+            Self::KP0054KeyObjectSshCaInvalid => None,
+            Self::KP0055KeyObjectNoActiveSshCa => None,
+            Self::KP0056KeyObjectSshCertificateIssue => None,
+            Self::KP0057KeyObjectSelfTestSshCertificateInvalid => Some("The ssh certificate issued by the certificate authority did not verify.".into()),
+            Self::KP0058KeyObjectSaml2SigningGeneration => None,
+            Self::KP0059KeyObjectSaml2SigningInvalid => None,
+            Self::KP0060KeyObjectNoActiveSaml2Signing => None,
+            Self::KP0061KeyObjectSelfTestSaml2SignatureInvalid => Some("The saml2 signature did not verify with the signing certificate.".into()),
+            Self::KP0062KeyObjectKerberosInvalid => None,
+            Self::KP0063KeyObjectNoActiveKerberosKey => None,
+            Self::KP0064KeyObjectKerberosTicketIssue => None,
+            Self::KP0065KeyObjectSelfTestKerberosKeyInvalid => Some("Data encrypted with a kerberos service key did not decrypt.".into()),
+            Self::KP0066KeyObjectJwsRs256Generation => None,
+            Self::KP0067KeyObjectJwsRs256DerInvalid => None,
+            Self::KP0068KeyObjectJwsRs256Signature => None,
+            Self::KP0069KeyObjectJwsRs256PublicJwkInvalid => None,
+            Self::KP0070KeyObjectNoJwsSigningKey => Some("The key object has no signing keys for the requested algorithm.".into()),
             Self::KU001InitWhileSessionActive => Some("The session was active when the init function was called.".into()),
             Self::KU002ContinueWhileSessionInActive => Some("Attempted to continue auth session while current session is inactive".into()),
             Self::KU003PamAuthFailed => Some("Failed PAM account authentication step".into()),
             Self::KU004PamInitFailed => Some("Failed to initialise PAM authentication".into()),
             Self::KU005ErrorCheckingAccount => Some("Error checking account".into()),
             Self::KU006OnlyRootAllowed => Some("Only root is allowed to perform this operation".into()),
+            Self::KU007MachineEnrollmentFailed => Some("Failed to enroll this machine with the identity provider".into()),
+            Self::KU008KerberosKeytabFailed => Some("Failed to retrieve the kerberos keytab of this machine".into()),
             Self::LD0001AnonymousNotAllowed => Some("Anonymous is not allowed to access LDAP with this method.".into()),
             Self::MG0001InvalidReMigrationLevel => None,
             Self::MG0002RaiseDomainLevelExceedsMaximum => None,
@@ -464,7 +589,19 @@ impl OperationError {
             Self::MG0007Oauth2StrictConstraintsNotMet => Some("Migration Constraints Not Met - All OAuth2 clients must have strict-redirect-uri mode enabled.".into()),
             Self::MG0008SkipUpgradeAttempted => Some("Skip Upgrade Attempted.".into()),
             Self::MG0009InvalidTargetLevelForBootstrap => Some("The request target domain level was not valid for bootstrapping a new server instance".into()),
+            Self::OA0001RedirectUriNotRegistered => Some("The redirect uri is not registered for this client. It must exactly match one of the client's redirect uris.".into()),
+            Self::OA0002RedirectUriOriginNotRegistered => Some("The origin of the redirect uri is not registered for this client.".into()),
+            Self::OA0003RedirectUriHttpsRequired => Some("The redirect uri must use https, as the client has https redirect uris.".into()),
+            Self::OA0004RedirectUriLoopbackNotRegistered => Some("The loopback redirect uri does not match a registered loopback redirect uri on any port.".into()),
+            Self::OA0005RedirectUriPrivateUseSchemeInvalid => Some("The scheme of the redirect uri must be a reverse domain name that the client controls, such as com.example.app.".into()),
+            Self::OA0006RedirectUriPrivateUseSchemeNotRegistered => Some("The private-use scheme of the redirect uri is not registered for this client.".into()),
             Self::PL0001GidOverlapsSystemRange => None,
+            Self::PL0002ContractorSponsorInvalid => Some("The sponsor of a contractor must be a person that is not a contractor themself.".into()),
+            Self::PL0003SshPublicKeyPolicyDenied => Some("The ssh public key type or length is not permitted by the account policy.".into()),
+            Self::PL0004GroupParentInvalid => Some("The parent of a group must be another group that is not high privilege, and not one of its own subgroups.".into()),
+            Self::SA0001AuthnRequestInvalid => Some("The SAML authentication request is invalid or uses an unsupported binding.".into()),
+            Self::SA0002AcsUrlNotRegistered => Some("The assertion consumer service url is not registered for this service provider.".into()),
+            Self::SA0003AuthnRequestIssuerMismatch => Some("The issuer of the SAML authentication request does not match the entity id of this service provider.".into()),
             Self::SC0001IncomingSshPublicKey => None,
             Self::SC0002ReferenceSyntaxInvalid => Some("A SCIM Reference Set contained invalid syntax and can not be processed.".into()),
             Self::SC0003MailSyntaxInvalid => Some("A SCIM Mail Address contained invalid syntax".into()),
@@ -496,6 +633,15 @@ impl OperationError {
             Self::UI0001ChallengeSerialisation => Some("The WebAuthn challenge was unable to be serialised.".into()),
             Self::UI0002InvalidState => Some("The credential update process returned an invalid state transition.".into()),
             Self::UI0003InvalidOauth2Resume => Some("The server attemped to resume OAuth2, but no OAuth2 session is in progress.".into()),
+            Self::UI0004InvalidSaml2Resume => Some("The server attempted to resume SAML, but no SAML authentication request is in progress.".into()),
+            Self::GR0001GroupMembershipNotRequestable => Some("Membership of this group can't be requested.".into()),
+            Self::GR0002GroupMembershipRequestExists => Some("The account is already a member of this group, or has a pending request to join it.".into()),
+            Self::GR0003GroupMembershipRequestDecided => Some("This membership request has already been approved or denied.".into()),
+            Self::HT0001IdempotencyKeyInProgress => Some("A request with this idempotency key is still in progress.".into()),
+            Self::HT0002IdempotencyKeyReused => Some("This idempotency key was used by a different request.".into()),
+            Self::HT0003IdempotencyRequestTooLarge => Some("The request body is too large to be used with an idempotency key.".into()),
+            Self::HT0004IdempotencyKeyInvalid => Some("The idempotency key must be between 1 and 255 visible ascii characters.".into()),
+            Self::HT0005EntryVersionMismatch => Some("The entry does not exist, or has changed since the version given in If-Match was read.".into()),
             Self::VL0001ValueSshPublicKeyString => None,
             Self::VS0001IncomingReplSshPublicKey => None,
             Self::VS0002CertificatePublicKeyDigest |
@@ -506,6 +652,219 @@ impl OperationError {
     }
 }
 
+/// A stable classification of an [OperationError]. Unlike the operation error itself, which
+/// may gain or change variants between releases, these codes are only ever added to so that
+/// automation can reliably branch on them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotAuthenticated,
+    SessionExpired,
+    AccessDenied,
+    SystemProtected,
+    NotFound,
+    Conflict,
+    PreconditionFailed,
+    InvalidRequest,
+    InvalidAttribute,
+    SchemaViolation,
+    PasswordQuality,
+    RateLimited,
+    Unavailable,
+    InternalError,
+    /// A code sent by a newer server that this client does not know.
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NotAuthenticated => "not_authenticated",
+            Self::SessionExpired => "session_expired",
+            Self::AccessDenied => "access_denied",
+            Self::SystemProtected => "system_protected",
+            Self::NotFound => "not_found",
+            Self::Conflict => "conflict",
+            Self::PreconditionFailed => "precondition_failed",
+            Self::InvalidRequest => "invalid_request",
+            Self::InvalidAttribute => "invalid_attribute",
+            Self::SchemaViolation => "schema_violation",
+            Self::PasswordQuality => "password_quality",
+            Self::RateLimited => "rate_limited",
+            Self::Unavailable => "unavailable",
+            Self::InternalError => "internal_error",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// The HTTP status code that is returned with this error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::NotAuthenticated | Self::SessionExpired => 401,
+            Self::AccessDenied | Self::SystemProtected => 403,
+            Self::NotFound => 404,
+            Self::Conflict => 409,
+            Self::PreconditionFailed => 412,
+            Self::InvalidRequest
+            | Self::InvalidAttribute
+            | Self::SchemaViolation
+            | Self::PasswordQuality => 400,
+            Self::RateLimited => 429,
+            Self::Unavailable => 503,
+            Self::InternalError | Self::Unknown => 500,
+        }
+    }
+
+    /// A short, human readable summary of the error.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::NotAuthenticated => "Authentication is required",
+            Self::SessionExpired => "The session has expired",
+            Self::AccessDenied => "Access was denied",
+            Self::SystemProtected => "The entry or attribute is protected by the system",
+            Self::NotFound => "No matching entries were found",
+            Self::Conflict => "The request conflicts with an existing entry",
+            Self::PreconditionFailed => "The entry has changed since it was read",
+            Self::InvalidRequest => "The request is invalid",
+            Self::InvalidAttribute => "An attribute or value in the request is invalid",
+            Self::SchemaViolation => "The request does not conform to the schema",
+            Self::PasswordQuality => "The password does not meet the quality requirements",
+            Self::RateLimited => "Too many requests have been made",
+            Self::Unavailable => "The server is temporarily not accepting changes",
+            Self::InternalError => "An internal error occurred",
+            Self::Unknown => "An unknown error occurred",
+        }
+    }
+
+    /// Remediation advice for the person or tool that made the request.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::NotAuthenticated => Some("Authenticate, or provide a valid bearer token."),
+            Self::SessionExpired => Some("Authenticate again to start a new session."),
+            Self::AccessDenied => Some(
+                "Check that you have the privileges for this operation. Changes require a read-write session, which can be gained by reauthenticating.",
+            ),
+            Self::SystemProtected => {
+                Some("This is managed by the server and can't be changed or removed.")
+            }
+            Self::NotFound => Some("Check the name or uuid of the requested entry."),
+            Self::Conflict => Some(
+                "Another entry already has this value, or a request with the same idempotency key is still in progress.",
+            ),
+            Self::PreconditionFailed => {
+                Some("Read the entry again to get its current version, and retry the change.")
+            }
+            Self::InvalidRequest => Some("Check the request against the API documentation."),
+            Self::InvalidAttribute => Some("Check the attribute names and their values."),
+            Self::SchemaViolation => {
+                Some("Check that the entry has the classes and attributes its schema requires.")
+            }
+            Self::PasswordQuality => {
+                Some("Choose a longer password, or one that is not a common or known password.")
+            }
+            Self::RateLimited => Some("Wait before retrying the request."),
+            Self::Unavailable => {
+                Some("The server is in maintenance. Retry the change once maintenance has ended.")
+            }
+            Self::InternalError => {
+                Some("Report this error and its operation id to your server administrator.")
+            }
+            Self::Unknown => None,
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&OperationError> for ErrorCode {
+    fn from(err: &OperationError) -> Self {
+        match err {
+            OperationError::NotAuthenticated => Self::NotAuthenticated,
+            OperationError::SessionExpired => Self::SessionExpired,
+            OperationError::AccessDenied => Self::AccessDenied,
+            OperationError::SystemProtectedObject => Self::SystemProtected,
+            OperationError::NoMatchingEntries => Self::NotFound,
+            OperationError::UniqueConstraintViolation
+            | OperationError::Plugin(PluginError::AttrUnique(_))
+            | OperationError::HT0001IdempotencyKeyInProgress
+            | OperationError::GR0002GroupMembershipRequestExists => Self::Conflict,
+            OperationError::HT0005EntryVersionMismatch => Self::PreconditionFailed,
+            OperationError::EmptyRequest
+            | OperationError::FilterParseError
+            | OperationError::HT0002IdempotencyKeyReused
+            | OperationError::HT0003IdempotencyRequestTooLarge
+            | OperationError::HT0004IdempotencyKeyInvalid
+            | OperationError::CU0003WebauthnUserNotVerified
+            | OperationError::CU0008IntentTokenNoMail
+            | OperationError::SA0001AuthnRequestInvalid
+            | OperationError::SA0002AcsUrlNotRegistered
+            | OperationError::SA0003AuthnRequestIssuerMismatch
+            | OperationError::GR0001GroupMembershipNotRequestable
+            | OperationError::GR0003GroupMembershipRequestDecided => Self::InvalidRequest,
+            OperationError::InvalidAttribute(_)
+            | OperationError::InvalidAttributeName(_)
+            | OperationError::VL0001ValueSshPublicKeyString => Self::InvalidAttribute,
+            OperationError::SchemaViolation(_) => Self::SchemaViolation,
+            OperationError::PasswordQuality(_) => Self::PasswordQuality,
+            OperationError::CU0007PasswordCheckRateLimited
+            | OperationError::KG006WhitePagesRateLimited => Self::RateLimited,
+            OperationError::KG008MaintenanceModeReadOnly => Self::Unavailable,
+            _ => Self::InternalError,
+        }
+    }
+}
+
+/// An [RFC 9457](https://www.rfc-editor.org/rfc/rfc9457) problem details document. This is
+/// the body of all error responses from the HTTP API, and is sent with the content type
+/// `application/problem+json`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ProblemDetails {
+    /// A URI identifying the problem type, derived from the error code.
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: Option<String>,
+    /// The stable code of this error.
+    pub code: ErrorCode,
+    /// The operation id of the request, for correlation with the server logs.
+    pub opid: Option<String>,
+    /// Remediation advice for this error.
+    pub hint: Option<String>,
+    /// The underlying operation error. Prefer `code` when branching on errors, as the
+    /// variants of this may change between releases.
+    pub operation_error: Option<OperationError>,
+}
+
+impl ProblemDetails {
+    pub fn new(code: ErrorCode, detail: Option<String>) -> Self {
+        ProblemDetails {
+            problem_type: format!("urn:kanidm:problem:{}", code),
+            title: code.title().to_string(),
+            status: code.status(),
+            detail,
+            code,
+            opid: None,
+            hint: code.hint().map(str::to_string),
+            operation_error: None,
+        }
+    }
+}
+
+impl From<OperationError> for ProblemDetails {
+    fn from(err: OperationError) -> Self {
+        let mut problem = ProblemDetails::new(ErrorCode::from(&err), Some(err.to_string()));
+        problem.operation_error = Some(err);
+        problem
+    }
+}
+
 #[test]
 fn test_operationerror_as_nice_string() {
     assert_eq!(
@@ -525,3 +884,21 @@ fn test_operationerror_as_nice_string() {
         "CorruptedEntry(12345)".to_string()
     );
 }
+
+#[test]
+fn test_problem_details_from_operationerror() {
+    let problem = ProblemDetails::from(OperationError::NoMatchingEntries);
+    assert_eq!(problem.code, ErrorCode::NotFound);
+    assert_eq!(problem.status, 404);
+    assert_eq!(problem.problem_type, "urn:kanidm:problem:not_found");
+
+    let json = serde_json::to_value(&problem).expect("Failed to serialise problem");
+    assert_eq!(json["type"], "urn:kanidm:problem:not_found");
+    assert_eq!(json["code"], "not_found");
+    assert!(json.get("opid").is_none());
+
+    // Codes from a newer server must still decode.
+    let code: ErrorCode =
+        serde_json::from_str("\"some_future_code\"").expect("Failed to decode code");
+    assert_eq!(code, ErrorCode::Unknown);
+}
@@ -3,7 +3,9 @@
 //! Items defined in this module *may* change between releases without notice.
 
 use crate::constants::{
-    CONTENT_TYPE_GIF, CONTENT_TYPE_JPG, CONTENT_TYPE_PNG, CONTENT_TYPE_SVG, CONTENT_TYPE_WEBP,
+    ATTR_DISPLAYNAME, ATTR_GIDNUMBER, ATTR_LEGALNAME, ATTR_LOGINSHELL, ATTR_MAIL, ATTR_NAME,
+    ATTR_SPN, ATTR_UUID, CONTENT_TYPE_GIF, CONTENT_TYPE_JPG, CONTENT_TYPE_PNG, CONTENT_TYPE_SVG,
+    CONTENT_TYPE_WEBP,
 };
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
@@ -15,21 +17,33 @@ use uuid::Uuid;
 
 use num_enum::TryFromPrimitive;
 
+mod accountpolicy;
+mod announcement;
 mod credupdate;
 mod error;
 mod raw;
 mod token;
+mod webhook;
 
+pub use self::accountpolicy::*;
+pub use self::announcement::*;
 pub use self::credupdate::*;
 pub use self::error::*;
 pub use self::raw::*;
 pub use self::token::*;
+pub use self::webhook::*;
 
 pub const COOKIE_AUTH_SESSION_ID: &str = "auth-session-id";
 pub const COOKIE_BEARER_TOKEN: &str = "bearer";
 pub const COOKIE_CU_SESSION_TOKEN: &str = "cu-session-token";
-pub const COOKIE_USERNAME: &str = "username";
+pub const COOKIE_REMEMBER_ME: &str = "remember-me";
 pub const COOKIE_OAUTH2_REQ: &str = "o2-authreq";
+pub const COOKIE_SAML2_REQ: &str = "saml2-authnreq";
+/// Set by the browser, not the server, with a proof that it holds the key its session is bound to.
+pub const COOKIE_SESSION_PROOF: &str = "session-proof";
+/// The id of the session that is bound to the browser's key. This is readable by the browser, so
+/// that it can include it in its proofs.
+pub const COOKIE_BOUND_SESSION_ID: &str = "bound-session-id";
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 /// This is a description of a linked or connected application for a user. This is
@@ -44,6 +58,32 @@ pub enum AppLink {
     },
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+/// A consent that a person has granted to an OAuth2 client. While the client requests the
+/// same scopes, the person isn't asked to consent again.
+pub struct Oauth2Consent {
+    pub client_name: String,
+    pub display_name: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+/// An application password of a person. The password itself is only shown once when it
+/// is generated.
+pub struct ApplicationPassword {
+    pub uuid: Uuid,
+    pub application: String,
+    pub label: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+/// A request to generate an application password. If the person already has a password
+/// with the same label for the application, it is replaced.
+pub struct ApplicationPasswordRequest {
+    pub application: String,
+    pub label: String,
+}
+
 #[derive(
     Debug, Serialize, Deserialize, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, ToSchema,
 )]
@@ -82,6 +122,43 @@ impl FromStr for UiHint {
     }
 }
 
+/// The colour theme of the web interface. A domain sets the default theme, which a person
+/// may override for themself.
+#[derive(
+    Debug, Serialize, Deserialize, Copy, Clone, Default, Eq, PartialEq, Hash, ToSchema, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum UiTheme {
+    /// Follow the colour scheme preference of the browser.
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+impl fmt::Display for UiTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UiTheme::Auto => write!(f, "auto"),
+            UiTheme::Light => write!(f, "light"),
+            UiTheme::Dark => write!(f, "dark"),
+        }
+    }
+}
+
+impl FromStr for UiTheme {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(UiTheme::Auto),
+            "light" => Ok(UiTheme::Light),
+            "dark" => Ok(UiTheme::Dark),
+            _ => Err(()),
+        }
+    }
+}
+
 // State machine states and transitions for the identity verification system feature!
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
 pub enum IdentifyUserRequest {
@@ -182,7 +259,7 @@ impl ImageValue {
 }
 
 #[repr(u32)]
-#[derive(Debug, Copy, Clone, Deserialize, Default, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Default, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 /// Filesystem type object, used for tuning database parameters.
 pub enum FsType {
@@ -224,6 +301,496 @@ pub enum Oauth2ClaimMapJoin {
     Array,
 }
 
+/// How the redirect uri of an authorisation request is validated against the redirect uris of
+/// an OAuth2 client. Every mode accepts a redirect uri that exactly matches one that is
+/// registered, and the native application modes add an allowance on top of this.
+#[derive(
+    Debug, Serialize, Deserialize, Copy, Clone, Default, Eq, PartialEq, Hash, ToSchema, ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Oauth2RedirectUriMode {
+    /// The redirect uri must exactly match a registered redirect uri.
+    #[default]
+    Exact,
+    /// A loopback redirect uri may use any port, as native applications listen on an ephemeral
+    /// port. ref <https://www.rfc-editor.org/rfc/rfc8252#section-7.3>
+    LoopbackAnyPort,
+    /// A redirect uri may use any path of a registered private-use scheme, which must be a
+    /// reverse domain name. ref <https://www.rfc-editor.org/rfc/rfc8252#section-7.1>
+    PrivateUseScheme,
+}
+
+impl fmt::Display for Oauth2RedirectUriMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Oauth2RedirectUriMode::Exact => write!(f, "exact"),
+            Oauth2RedirectUriMode::LoopbackAnyPort => write!(f, "loopback_any_port"),
+            Oauth2RedirectUriMode::PrivateUseScheme => write!(f, "private_use_scheme"),
+        }
+    }
+}
+
+impl FromStr for Oauth2RedirectUriMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Oauth2RedirectUriMode::Exact),
+            "loopback_any_port" => Ok(Oauth2RedirectUriMode::LoopbackAnyPort),
+            "private_use_scheme" => Ok(Oauth2RedirectUriMode::PrivateUseScheme),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The algorithm that the tokens of an OAuth2 client are signed with. The signing keys are
+/// held in the key object of the client, so that they can be rotated independently of every
+/// other client.
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Hash, ToSchema, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Oauth2JwtSignAlg {
+    Es256,
+    /// For clients that are unable to validate ES256 signatures.
+    Rs256,
+}
+
+impl fmt::Display for Oauth2JwtSignAlg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Oauth2JwtSignAlg::Es256 => write!(f, "es256"),
+            Oauth2JwtSignAlg::Rs256 => write!(f, "rs256"),
+        }
+    }
+}
+
+impl FromStr for Oauth2JwtSignAlg {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "es256" => Ok(Oauth2JwtSignAlg::Es256),
+            "rs256" => Ok(Oauth2JwtSignAlg::Rs256),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A grant type that an OAuth2 client may use at the token endpoint.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    ToSchema,
+    ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum Oauth2GrantType {
+    AuthorizationCode,
+    ClientCredentials,
+    RefreshToken,
+    DeviceCode,
+    TokenExchange,
+}
+
+impl fmt::Display for Oauth2GrantType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Oauth2GrantType::AuthorizationCode => write!(f, "authorization_code"),
+            Oauth2GrantType::ClientCredentials => write!(f, "client_credentials"),
+            Oauth2GrantType::RefreshToken => write!(f, "refresh_token"),
+            Oauth2GrantType::DeviceCode => write!(f, "device_code"),
+            Oauth2GrantType::TokenExchange => write!(f, "token_exchange"),
+        }
+    }
+}
+
+impl FromStr for Oauth2GrantType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "authorization_code" => Ok(Oauth2GrantType::AuthorizationCode),
+            "client_credentials" => Ok(Oauth2GrantType::ClientCredentials),
+            "refresh_token" => Ok(Oauth2GrantType::RefreshToken),
+            "device_code" => Ok(Oauth2GrantType::DeviceCode),
+            "token_exchange" => Ok(Oauth2GrantType::TokenExchange),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A response type that an OAuth2 client may request at the authorisation endpoint.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    ToSchema,
+    ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum Oauth2ResponseType {
+    Code,
+    Token,
+    IdToken,
+}
+
+impl fmt::Display for Oauth2ResponseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Oauth2ResponseType::Code => write!(f, "code"),
+            Oauth2ResponseType::Token => write!(f, "token"),
+            Oauth2ResponseType::IdToken => write!(f, "id_token"),
+        }
+    }
+}
+
+impl FromStr for Oauth2ResponseType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "code" => Ok(Oauth2ResponseType::Code),
+            "token" => Ok(Oauth2ResponseType::Token),
+            "id_token" => Ok(Oauth2ResponseType::IdToken),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A human readable title and description of an OAuth2 scope, shown to users on the consent
+/// screen. These are stored in the form `scope[@language]=title[|description]`, where a
+/// description without a language is used when no better match for the user is found.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Oauth2ScopeDescription {
+    pub scope: String,
+    pub language: Option<String>,
+    pub title: String,
+    pub description: Option<String>,
+}
+
+impl fmt::Display for Oauth2ScopeDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.scope)?;
+        if let Some(language) = &self.language {
+            write!(f, "@{language}")?;
+        }
+        write!(f, "={}", self.title)?;
+        if let Some(description) = &self.description {
+            write!(f, "|{description}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Oauth2ScopeDescription {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, text) = s.split_once('=').ok_or(())?;
+
+        let (scope, language) = match key.split_once('@') {
+            Some((scope, language)) => {
+                if language.is_empty()
+                    || !language
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-')
+                {
+                    return Err(());
+                }
+                (scope, Some(language.to_ascii_lowercase()))
+            }
+            None => (key, None),
+        };
+
+        let (title, description) = match text.split_once('|') {
+            Some((title, description)) => (title, Some(description.trim().to_string())),
+            None => (text, None),
+        };
+
+        let scope = scope.trim();
+        let title = title.trim();
+        if scope.is_empty() || scope.contains(char::is_whitespace) || title.is_empty() {
+            return Err(());
+        }
+
+        Ok(Oauth2ScopeDescription {
+            scope: scope.to_string(),
+            language,
+            title: title.to_string(),
+            description: description.filter(|d| !d.is_empty()),
+        })
+    }
+}
+
+/// The attributes of an account that may be referenced by an OAuth2 computed claim template.
+pub const OAUTH2_CLAIM_TEMPLATE_ATTRIBUTES: &[&str] = &[
+    ATTR_NAME,
+    ATTR_SPN,
+    ATTR_DISPLAYNAME,
+    ATTR_LEGALNAME,
+    ATTR_MAIL,
+    ATTR_UUID,
+    ATTR_GIDNUMBER,
+    ATTR_LOGINSHELL,
+];
+
+/// A part of an OAuth2 computed claim template.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Oauth2ClaimTemplatePart {
+    Text(String),
+    Attribute(String),
+}
+
+/// Where the values of an OAuth2 computed claim are sourced from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Oauth2ComputedClaimSource {
+    /// A template such as `{name}.{legalname}@example.com`. When an attribute has multiple
+    /// values, one value is produced for each of them. If an attribute is absent no value is
+    /// produced.
+    Template(Vec<Oauth2ClaimTemplatePart>),
+    /// The names of the groups that the account is a member of.
+    GroupNames,
+}
+
+/// A transform applied to each value of an OAuth2 computed claim, in order.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Oauth2ClaimTransform {
+    Lowercase,
+    /// Remove this prefix from values that have it.
+    StripPrefix(String),
+    /// Only retain values that start with this prefix.
+    FilterPrefix(String),
+    /// Only retain values that match this pattern, where `*` matches any number of
+    /// characters and `?` matches a single character.
+    FilterGlob(String),
+}
+
+impl Oauth2ClaimTransform {
+    /// If `value` matches the glob `pattern`. The whole value must match.
+    /// ```
+    /// use kanidm_proto::internal::Oauth2ClaimTransform;
+    /// assert!(Oauth2ClaimTransform::glob_matches("app_*_admin", "app_grafana_admin"));
+    /// assert!(Oauth2ClaimTransform::glob_matches("role?", "role1"));
+    /// assert!(!Oauth2ClaimTransform::glob_matches("app_*", "idm_admins"));
+    /// assert!(!Oauth2ClaimTransform::glob_matches("role?", "role10"));
+    /// ```
+    pub fn glob_matches(pattern: &str, value: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let value: Vec<char> = value.chars().collect();
+
+        let (mut p, mut v) = (0, 0);
+        // The position of the last `*`, and the value position it was matched up to.
+        let mut backtrack = None;
+
+        while v < value.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    backtrack = Some((p, v));
+                    p += 1;
+                }
+                Some('?') => {
+                    p += 1;
+                    v += 1;
+                }
+                Some(c) if *c == value[v] => {
+                    p += 1;
+                    v += 1;
+                }
+                _ => match backtrack {
+                    // Let the last `*` consume one more character and retry.
+                    Some((star_p, star_v)) => {
+                        backtrack = Some((star_p, star_v + 1));
+                        p = star_p + 1;
+                        v = star_v + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        pattern[p..].iter().all(|c| *c == '*')
+    }
+}
+
+/// A claim whose values are computed from the account that is authorising. These are stored
+/// in the form `claim=source[|option]...`, where the source is `template:<template>` or
+/// `groups`, and the options are `lowercase`, `strip_prefix:<prefix>`, `filter_prefix:<prefix>`,
+/// `filter_glob:<pattern>` and `join:<separator>`. A separator of `array` emits a json array.
+/// If no separator is given, group names are emitted as a json array, and template values are
+/// joined with `,`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Oauth2ComputedClaim {
+    pub claim: String,
+    pub source: Oauth2ComputedClaimSource,
+    pub transforms: Vec<Oauth2ClaimTransform>,
+    /// The separator that values are joined with. If `None` the values are a json array.
+    pub join: Option<String>,
+}
+
+impl fmt::Display for Oauth2ComputedClaim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}=", self.claim)?;
+        match &self.source {
+            Oauth2ComputedClaimSource::Template(parts) => {
+                write!(f, "template:")?;
+                for part in parts {
+                    match part {
+                        Oauth2ClaimTemplatePart::Text(text) => write!(f, "{text}")?,
+                        Oauth2ClaimTemplatePart::Attribute(attr) => write!(f, "{{{attr}}}")?,
+                    }
+                }
+            }
+            Oauth2ComputedClaimSource::GroupNames => write!(f, "groups")?,
+        }
+        for transform in &self.transforms {
+            match transform {
+                Oauth2ClaimTransform::Lowercase => write!(f, "|lowercase")?,
+                Oauth2ClaimTransform::StripPrefix(prefix) => write!(f, "|strip_prefix:{prefix}")?,
+                Oauth2ClaimTransform::FilterPrefix(prefix) => write!(f, "|filter_prefix:{prefix}")?,
+                Oauth2ClaimTransform::FilterGlob(pattern) => write!(f, "|filter_glob:{pattern}")?,
+            }
+        }
+        match &self.join {
+            Some(separator) => write!(f, "|join:{separator}"),
+            None => write!(f, "|join:array"),
+        }
+    }
+}
+
+impl FromStr for Oauth2ComputedClaim {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (claim, expression) = s.split_once('=').ok_or(())?;
+
+        let claim = claim.trim();
+        if claim.is_empty() || !claim.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(());
+        }
+
+        let mut options = expression.split('|');
+
+        let source = match options.next().map(str::trim) {
+            Some("groups") => Oauth2ComputedClaimSource::GroupNames,
+            Some(source) => {
+                let template = source.strip_prefix("template:").ok_or(())?;
+                Oauth2ComputedClaimSource::Template(parse_claim_template(template)?)
+            }
+            None => return Err(()),
+        };
+
+        let mut transforms = Vec::new();
+        let mut join = None;
+
+        for option in options {
+            let (name, arg) = match option.split_once(':') {
+                Some((name, arg)) => (name.trim(), Some(arg)),
+                None => (option.trim(), None),
+            };
+
+            match (name, arg) {
+                ("lowercase", None) => transforms.push(Oauth2ClaimTransform::Lowercase),
+                ("strip_prefix", Some(prefix)) if !prefix.is_empty() => {
+                    transforms.push(Oauth2ClaimTransform::StripPrefix(prefix.to_string()))
+                }
+                ("filter_prefix", Some(prefix)) if !prefix.is_empty() => {
+                    transforms.push(Oauth2ClaimTransform::FilterPrefix(prefix.to_string()))
+                }
+                ("filter_glob", Some(pattern)) if !pattern.is_empty() => {
+                    transforms.push(Oauth2ClaimTransform::FilterGlob(pattern.to_string()))
+                }
+                ("join", Some(separator)) if join.is_none() => {
+                    join = Some(separator.to_string());
+                }
+                _ => return Err(()),
+            }
+        }
+
+        let join = match join.as_deref() {
+            Some("array") => None,
+            Some(separator) => Some(separator.to_string()),
+            None => match source {
+                Oauth2ComputedClaimSource::Template(_) => Some(",".to_string()),
+                Oauth2ComputedClaimSource::GroupNames => None,
+            },
+        };
+
+        Ok(Oauth2ComputedClaim {
+            claim: claim.to_string(),
+            source,
+            transforms,
+            join,
+        })
+    }
+}
+
+fn parse_claim_template(template: &str) -> Result<Vec<Oauth2ClaimTemplatePart>, ()> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let (text, tail) = rest.split_at(start);
+        if text.contains('}') {
+            return Err(());
+        }
+        if !text.is_empty() {
+            parts.push(Oauth2ClaimTemplatePart::Text(text.to_string()));
+        }
+
+        let (attr, tail) = tail[1..].split_once('}').ok_or(())?;
+        let attr = attr.trim().to_lowercase();
+        if !OAUTH2_CLAIM_TEMPLATE_ATTRIBUTES.contains(&attr.as_str()) {
+            return Err(());
+        }
+        parts.push(Oauth2ClaimTemplatePart::Attribute(attr));
+        rest = tail;
+    }
+
+    if rest.contains('}') {
+        return Err(());
+    }
+    if !rest.is_empty() {
+        parts.push(Oauth2ClaimTemplatePart::Text(rest.to_string()));
+    }
+
+    // A template without any attributes is a constant, which a claim map already provides.
+    if !parts
+        .iter()
+        .any(|part| matches!(part, Oauth2ClaimTemplatePart::Attribute(_)))
+    {
+        return Err(());
+    }
+
+    Ok(parts)
+}
+
+/// A request to replace the secret of a confidential OAuth2 client. The previous secret remains
+/// valid for `previous_secret_validity` seconds so that clients can be updated without an outage.
+/// If this is not set the server default is used.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct Oauth2BasicSecretRotateRequest {
+    pub previous_secret_validity: Option<u64>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DomainInfo {
     pub name: String,
@@ -261,6 +828,264 @@ pub struct DomainUpgradeCheckItem {
     pub affected_entries: Vec<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SelfTestStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for SelfTestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelfTestStatus::Pass => write!(f, "pass"),
+            SelfTestStatus::Warn => write!(f, "warn"),
+            SelfTestStatus::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum SelfTestCheck {
+    KeyProvider,
+    KeyObject,
+    PasswordHashCost,
+    ClockSkew,
+    Database,
+    Replication,
+    TlsCertificate,
+    TlsName,
+    FilePermissions,
+    ClockSync,
+    Dns,
+}
+
+impl fmt::Display for SelfTestCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelfTestCheck::KeyProvider => write!(f, "key provider"),
+            SelfTestCheck::KeyObject => write!(f, "key object"),
+            SelfTestCheck::PasswordHashCost => write!(f, "password hash cost"),
+            SelfTestCheck::ClockSkew => write!(f, "clock skew"),
+            SelfTestCheck::Database => write!(f, "database"),
+            SelfTestCheck::Replication => write!(f, "replication"),
+            SelfTestCheck::TlsCertificate => write!(f, "tls certificate"),
+            SelfTestCheck::TlsName => write!(f, "tls name"),
+            SelfTestCheck::FilePermissions => write!(f, "file permissions"),
+            SelfTestCheck::ClockSync => write!(f, "clock sync"),
+            SelfTestCheck::Dns => write!(f, "dns"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SelfTestItem {
+    pub check: SelfTestCheck,
+    pub status: SelfTestStatus,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SelfTestReport {
+    pub items: Vec<SelfTestItem>,
+}
+
+impl SelfTestReport {
+    /// The most severe status of any item in this report.
+    pub fn status(&self) -> SelfTestStatus {
+        self.items
+            .iter()
+            .map(|item| item.status)
+            .max()
+            .unwrap_or(SelfTestStatus::Pass)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReindexState {
+    /// No online reindex has been started since the server started.
+    #[default]
+    Idle,
+    Running,
+    Complete,
+    Failed,
+}
+
+impl fmt::Display for ReindexState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReindexState::Idle => write!(f, "idle"),
+            ReindexState::Running => write!(f, "running"),
+            ReindexState::Complete => write!(f, "complete"),
+            ReindexState::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// The progress of an online reindex. Indexes are rebuilt one attribute at a time.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReindexStatus {
+    pub state: ReindexState,
+    /// The attribute whose indexes are being rebuilt, or that failed to rebuild.
+    pub attribute: Option<String>,
+    /// The number of attributes whose indexes have been rebuilt.
+    pub completed: usize,
+    /// The number of attributes that have indexes.
+    pub total: usize,
+}
+
+/// An index that searches would have used had it existed, and what these searches cost
+/// without it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexRecommendation {
+    pub attribute: String,
+    /// The index type as it is named in the schema, such as `EQUALITY`.
+    pub index_type: String,
+    /// The number of searches that had a term that could have used this index.
+    pub searches: u64,
+    /// The number of candidate entries these searches had to test.
+    pub candidates: u64,
+    /// The time spent in these searches, in milliseconds.
+    pub time_ms: u64,
+}
+
+/// Statistics of the searches resolved by the query planner since the server started.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct QueryAnalysisReport {
+    pub searches: u64,
+    /// The number of searches that took longer than the slow search threshold.
+    pub slow_searches: u64,
+    /// The number of filter terms that were resolved by an index.
+    pub index_hits: u64,
+    /// The number of filter terms that had no index available.
+    pub index_misses: u64,
+    /// The number of candidate entries loaded by searches to be tested.
+    pub candidates: u64,
+    pub results: u64,
+    pub time_ms: u64,
+    /// Missing indexes, with the most costly first.
+    pub recommendations: Vec<IndexRecommendation>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaAttributeReport {
+    pub name: String,
+    pub uuid: Uuid,
+    pub description: String,
+    /// The syntax as it is named in the schema, such as `UTF8STRING_INAME`.
+    pub syntax: String,
+    pub multivalue: bool,
+    pub unique: bool,
+    pub index: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaClassReport {
+    pub name: String,
+    pub uuid: Uuid,
+    pub description: String,
+    /// Both the system and the administrator defined attributes that must be present.
+    pub must: Vec<String>,
+    /// Both the system and the administrator defined attributes that may be present.
+    pub may: Vec<String>,
+}
+
+/// The schema that is currently loaded by the server, sorted by name.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SchemaReport {
+    pub attributes: Vec<SchemaAttributeReport>,
+    pub classes: Vec<SchemaClassReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeyReport {
+    pub key_id: String,
+    pub usage: String,
+    pub status: String,
+    /// The time the key is valid from, in seconds since the epoch.
+    pub valid_from: u64,
+}
+
+/// A key object, such as the domain or an oauth2 client, and the keys it holds.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeyObjectReport {
+    pub uuid: Uuid,
+    /// The spn or name of the entry, if it has one.
+    pub name: Option<String>,
+    pub keys: Vec<KeyReport>,
+}
+
+/// The range of changes from one server that are held by this server.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplicationServerStatus {
+    pub server_uuid: Uuid,
+    /// If this is the server that answered the request.
+    pub local: bool,
+    /// The time of the oldest change held from this server, in seconds since the epoch.
+    pub oldest_change: u64,
+    /// The time of the newest change held from this server, in seconds since the epoch.
+    pub newest_change: u64,
+}
+
+/// The replication state of this server. Comparing the newest changes between servers
+/// shows if a server has fallen behind its partners.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReplicationStatusReport {
+    /// Changes older than this are trimmed from the changelog, and a server that has not
+    /// replicated since then must be refreshed. In seconds since the epoch.
+    pub trim_point: u64,
+    pub servers: Vec<ReplicationServerStatus>,
+}
+
+/// How the access of one account to one entry changes if the staged access controls were
+/// enforced. Rights are described as `search:<attr>`, `modify_present:<attr>`,
+/// `modify_removed:<attr>`, `modify_class:<class>` or `delete`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct AccessControlPreviewChange {
+    pub account: String,
+    pub target: String,
+    pub gained: Vec<String>,
+    pub lost: Vec<String>,
+}
+
+/// A comparison of the enforced access controls with the staged ones, evaluated for a sample
+/// of accounts and entries.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct AccessControlPreview {
+    /// The names of the access control profiles that have a staged change.
+    pub staged: Vec<String>,
+    pub accounts_sampled: u64,
+    pub entries_sampled: u64,
+    pub changes: Vec<AccessControlPreviewChange>,
+}
+
+/// A request to preview the staged access controls.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AccessControlPreviewQuery {
+    /// How many accounts and entries to sample. If absent, the server default is used.
+    pub sample: Option<usize>,
+}
+
+/// The response of the `/status/live` and `/status/ready` endpoints.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HealthReport {
+    /// The most severe status of any check.
+    pub status: SelfTestStatus,
+    pub checks: Vec<SelfTestItem>,
+}
+
+impl From<Vec<SelfTestItem>> for HealthReport {
+    fn from(checks: Vec<SelfTestItem>) -> Self {
+        let status = checks
+            .iter()
+            .map(|item| item.status)
+            .max()
+            .unwrap_or(SelfTestStatus::Pass);
+        HealthReport { status, checks }
+    }
+}
+
 #[test]
 fn test_fstype_deser() {
     assert_eq!(FsType::try_from("zfs"), Ok(FsType::Zfs));
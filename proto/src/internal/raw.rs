@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use utoipa::ToSchema;
+use uuid::Uuid;
 
+use crate::internal::OperationError;
 use crate::v1::Entry;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, ToSchema)]
@@ -99,6 +102,107 @@ impl ModifyRequest {
     }
 }
 
+/// The options of a write that can be checked without being made.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DryRunQuery {
+    /// Check the write in full, including by plugins, schema and access controls, and report
+    /// what it would do without committing it.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// The entries affected by a write that was checked but not committed. For a create or modify
+/// this is the entries as they would be after the write, and for a delete the entries that
+/// would be deleted. Only the attributes the requester is able to read are included.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct DryRunResponse {
+    pub entries: Vec<Entry>,
+}
+
+/// The phases of a bulk import. Every entry of an import is created before the references
+/// between them are added, so that the entries of an import can be in any order.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkImportPhase {
+    /// Create the entries, without the attributes that refer to other entries.
+    Entries,
+    /// Add the attributes that refer to other entries to the entries that were created.
+    References,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkImportRequest {
+    pub phase: BulkImportPhase,
+    pub entries: Vec<Entry>,
+}
+
+/// An entry of a bulk import request that could not be imported.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkImportFailure {
+    /// The position of the entry in the request.
+    pub index: usize,
+    pub error: OperationError,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkImportResponse {
+    /// The number of entries that were imported.
+    pub imported: usize,
+    pub failures: Vec<BulkImportFailure>,
+}
+
+/// The lifecycle state of an inspected entry.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryInspectState {
+    Live,
+    Recycled,
+    Tombstone,
+}
+
+/// The values of an attribute, and the change that last modified the attribute.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct EntryInspectAttr {
+    pub values: Vec<String>,
+    /// Absent for tombstones, as the history of their attributes is not retained.
+    pub changed_at: Option<String>,
+}
+
+/// The internal representation of an entry, including its change metadata. This is
+/// intended for administrators diagnosing replication or synchronisation issues.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct EntryInspectResponse {
+    pub uuid: Uuid,
+    pub state: EntryInspectState,
+    /// The change that created the entry, or for a tombstone, the change that deleted it.
+    pub state_at: String,
+    /// The sync account that this entry is provided by, if any.
+    pub sync_parent_uuid: Option<Uuid>,
+    pub attrs: BTreeMap<String, EntryInspectAttr>,
+}
+
+/// A recorded change to an entry, derived from the audit log.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct EntryHistoryEvent {
+    /// The time of the change in RFC3339 format.
+    pub time: String,
+    /// The audit event type, such as `entries_modified`.
+    pub event: String,
+    /// The identity that made the change, if it was made by an identity.
+    pub actor: Option<Uuid>,
+    /// The attributes that were changed. This is empty for creation and deletion.
+    pub attrs: Vec<String>,
+}
+
+/// The change history of an entry. The entry only retains the change that last modified
+/// each attribute, so earlier changes are only shown while they remain in the audit log.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct EntryHistoryResponse {
+    pub entry: EntryInspectResponse,
+    /// The changes to this entry, oldest first.
+    pub events: Vec<EntryHistoryEvent>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::Filter as ProtoFilter;
@@ -2,6 +2,7 @@ use super::UiHint;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::fmt;
+use std::str::FromStr;
 use time::OffsetDateTime;
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -48,6 +49,14 @@ pub struct UserAuthToken {
 
     pub limit_search_max_results: Option<u64>,
     pub limit_search_max_filter_test: Option<u64>,
+    /// If set, this session was issued to the member of idm_impersonation_admins with this
+    /// uuid, rather than to the person it represents.
+    pub impersonated_by: Option<Uuid>,
+    /// If set, the thumbprint of the key held by the browser that this session is bound to.
+    /// Requests that use this session must present a proof signed by that key.
+    pub session_key: Option<String>,
+    /// If set, the number of seconds this session may go unused before it expires.
+    pub idle_timeout: Option<u32>,
 }
 
 impl fmt::Display for UserAuthToken {
@@ -69,6 +78,15 @@ impl fmt::Display for UserAuthToken {
                 writeln!(f, "purpose: read write (expiry: none)")?
             }
         }
+        if let Some(impersonated_by) = self.impersonated_by {
+            writeln!(f, "impersonated by: {}", impersonated_by)?;
+        }
+        if let Some(session_key) = &self.session_key {
+            writeln!(f, "bound to key: {}", session_key)?;
+        }
+        if let Some(idle_timeout) = self.idle_timeout {
+            writeln!(f, "idle timeout: {}s", idle_timeout)?;
+        }
         Ok(())
     }
 }
@@ -119,6 +137,60 @@ pub struct ApiToken {
     // Defaults to ReadOnly if not present
     #[serde(default)]
     pub purpose: ApiTokenPurpose,
+    /// If not empty, the token may only modify the entries and attributes that these
+    /// permissions allow.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<ApiTokenPermission>,
+}
+
+/// A permission of a scoped api token. The token may modify `attrs` of entries that are of
+/// `class`, and if `name_prefix` is set, whose name starts with it. These are written in the
+/// form `class[:name_prefix]=attr[,attr]...` such as `group:app-=member`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct ApiTokenPermission {
+    pub class: String,
+    pub name_prefix: Option<String>,
+    pub attrs: Vec<String>,
+}
+
+impl fmt::Display for ApiTokenPermission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.class)?;
+        if let Some(name_prefix) = &self.name_prefix {
+            write!(f, ":{name_prefix}")?;
+        }
+        write!(f, "={}", self.attrs.join(","))
+    }
+}
+
+impl FromStr for ApiTokenPermission {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, attrs) = s.split_once('=').ok_or(())?;
+
+        let (class, name_prefix) = match target.split_once(':') {
+            Some((class, name_prefix)) => (class, Some(name_prefix.trim())),
+            None => (target, None),
+        };
+
+        let class = class.trim().to_lowercase();
+        let attrs: Vec<String> = attrs
+            .split(',')
+            .map(|attr| attr.trim().to_lowercase())
+            .filter(|attr| !attr.is_empty())
+            .collect();
+
+        if class.is_empty() || attrs.is_empty() || name_prefix.is_some_and(str::is_empty) {
+            return Err(());
+        }
+
+        Ok(ApiTokenPermission {
+            class,
+            name_prefix: name_prefix.map(str::to_string),
+            attrs,
+        })
+    }
 }
 
 impl fmt::Display for ApiToken {
@@ -137,10 +209,14 @@ impl fmt::Display for ApiToken {
                 )
                 .format(&time::format_description::well_known::Rfc3339)
                 .expect("Failed to format timestamp to RFC3339");
-            writeln!(f, "token expiry: {}", expiry_str)
+            writeln!(f, "token expiry: {}", expiry_str)?;
         } else {
-            writeln!(f, "token expiry: never")
+            writeln!(f, "token expiry: never")?;
         }
+        for permission in &self.permissions {
+            writeln!(f, "permission: {}", permission)?;
+        }
+        Ok(())
     }
 }
 
@@ -152,6 +228,57 @@ impl PartialEq for ApiToken {
 
 impl Eq for ApiToken {}
 
+/// An api token that a person issued to themself, along with when it was last used.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ApiTokenDetail {
+    pub token: ApiToken,
+    #[serde(with = "time::serde::timestamp::option")]
+    pub last_used: Option<time::OffsetDateTime>,
+}
+
+/// The api tokens of the current person, and if their account policy allows them to
+/// issue new tokens.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SelfApiTokens {
+    pub allowed: bool,
+    pub tokens: Vec<ApiTokenDetail>,
+}
+
+/// A session of the current person, and the device that it was created from.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SelfSession {
+    pub session_id: Uuid,
+    #[serde(with = "time::serde::timestamp")]
+    pub issued_at: time::OffsetDateTime,
+    #[serde(with = "time::serde::timestamp::option")]
+    pub expiry: Option<time::OffsetDateTime>,
+    /// If this is the session that is making the request.
+    pub current: bool,
+    pub source: Option<String>,
+    pub user_agent: Option<String>,
+    #[serde(with = "time::serde::timestamp::option")]
+    pub last_used: Option<time::OffsetDateTime>,
+}
+
+/// A session that the current person has with an OAuth2 client.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SelfOauth2Session {
+    pub session_id: Uuid,
+    pub client_name: String,
+    pub display_name: String,
+    #[serde(with = "time::serde::timestamp")]
+    pub issued_at: time::OffsetDateTime,
+    #[serde(with = "time::serde::timestamp::option")]
+    pub expiry: Option<time::OffsetDateTime>,
+}
+
+/// The active sessions of the current person.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SelfSessions {
+    pub sessions: Vec<SelfSession>,
+    pub oauth2_sessions: Vec<SelfOauth2Session>,
+}
+
 // This is similar to uat, but omits claims (they have no role in radius), and adds
 // the radius secret field.
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -161,6 +288,9 @@ pub struct RadiusAuthToken {
     pub uuid: String,
     pub secret: String,
     pub groups: Vec<Group>,
+    /// The serials of the client certificates of this account, for EAP-TLS.
+    #[serde(default)]
+    pub certificate_serials: Vec<String>,
 }
 
 impl fmt::Display for RadiusAuthToken {
@@ -169,6 +299,9 @@ impl fmt::Display for RadiusAuthToken {
         writeln!(f, "displayname: {}", self.displayname)?;
         writeln!(f, "uuid: {}", self.uuid)?;
         writeln!(f, "secret: {}", self.secret)?;
+        self.certificate_serials
+            .iter()
+            .try_for_each(|s| writeln!(f, "certificate_serial: {}", s))?;
         self.groups
             .iter()
             .try_for_each(|g| writeln!(f, "group: {}", g))
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use time::OffsetDateTime;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The change to an entry that caused a webhook event.
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookAction {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl fmt::Display for WebhookAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookAction::Created => write!(f, "created"),
+            WebhookAction::Modified => write!(f, "modified"),
+            WebhookAction::Deleted => write!(f, "deleted"),
+        }
+    }
+}
+
+impl FromStr for WebhookAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created" => Ok(WebhookAction::Created),
+            "modified" => Ok(WebhookAction::Modified),
+            "deleted" => Ok(WebhookAction::Deleted),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookDeliveryState {
+    /// The delivery has not yet succeeded, and will be retried.
+    Pending,
+    Delivered,
+    /// Every attempt to deliver the event failed.
+    Failed,
+}
+
+impl fmt::Display for WebhookDeliveryState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookDeliveryState::Pending => write!(f, "pending"),
+            WebhookDeliveryState::Delivered => write!(f, "delivered"),
+            WebhookDeliveryState::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl FromStr for WebhookDeliveryState {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(WebhookDeliveryState::Pending),
+            "delivered" => Ok(WebhookDeliveryState::Delivered),
+            "failed" => Ok(WebhookDeliveryState::Failed),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The status of the delivery of a single event to a webhook.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct WebhookDelivery {
+    /// The id of the event, which is sent to the webhook in the `X-Kanidm-Delivery` header.
+    pub id: Uuid,
+    pub webhook: Uuid,
+    pub action: WebhookAction,
+    pub target: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+    pub attempts: u32,
+    pub state: WebhookDeliveryState,
+    pub last_error: Option<String>,
+}
@@ -13,11 +13,17 @@ use serde_with::{
 use url::Url;
 use uuid::Uuid;
 
+use crate::internal::Oauth2ScopeDescription;
+
 /// How many seconds a device code is valid for.
 pub const OAUTH2_DEVICE_CODE_EXPIRY_SECONDS: u64 = 300;
 /// How often a client device can query the status of the token
 pub const OAUTH2_DEVICE_CODE_INTERVAL_SECONDS: u64 = 5;
 
+/// The token type identifier of an access token in a token exchange.
+/// ref <https://www.rfc-editor.org/rfc/rfc8693#section-3>
+pub const OAUTH2_TOKEN_TYPE_ACCESS_TOKEN: &str = "urn:ietf:params:oauth:token-type:access_token";
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CodeChallengeMethod {
     // default to plain if not requested as S256. Reject the auth?
@@ -129,6 +135,12 @@ pub enum AuthorisationResponse {
         scopes: BTreeSet<String>,
         // Extra PII that may be requested
         pii_scopes: BTreeSet<String>,
+        // Descriptions of the requested scopes, in any language they are available in.
+        #[serde(default)]
+        scope_descriptions: Vec<Oauth2ScopeDescription>,
+        // The names of the custom claims that will be released about the user.
+        #[serde(default)]
+        claims: BTreeSet<String>,
         // The users displayname (?)
         // pub display_name: String,
         // The token we need to be given back to allow this to proceed
@@ -165,6 +177,21 @@ pub enum GrantTypeReq {
         // #[serde_as(as = "Option<StringWithSeparator::<SpaceSeparator, String>>")]
         scope: Option<BTreeSet<String>>,
     },
+    /// ref <https://www.rfc-editor.org/rfc/rfc8693#section-2.1>
+    #[serde(rename = "urn:ietf:params:oauth:grant-type:token-exchange")]
+    TokenExchange {
+        // The token that represents the user the new token is requested for.
+        subject_token: String,
+        subject_token_type: String,
+        // The acting party is always the authenticated client, so these must be absent.
+        actor_token: Option<String>,
+        actor_token_type: Option<String>,
+        requested_token_type: Option<String>,
+        // The client_id of the client the new token is intended for.
+        audience: Option<String>,
+        #[serde_as(as = "Option<StringWithSeparator::<SpaceSeparator, String>>")]
+        scope: Option<BTreeSet<String>>,
+    },
 }
 
 /// An Access Token request. This requires a set of grant-type parameters to satisfy the request.
@@ -230,6 +257,36 @@ pub struct OAuth2RFC9068TokenExtensions {
 
     pub session_id: Uuid,
     pub parent_session_id: Option<Uuid>,
+
+    /// The party acting on behalf of the subject, if this token was issued by a token exchange.
+    pub act: Option<OAuth2TokenActor>,
+
+    /// The key this token is bound to, if it was issued to a client that proved possession of
+    /// a key with DPoP, or that authenticated with a tls client certificate.
+    #[serde(default)]
+    pub cnf: Option<OAuth2TokenConfirmation>,
+}
+
+/// The confirmation claim of a sender constrained token.
+/// ref <https://www.rfc-editor.org/rfc/rfc9449#section-6>
+/// ref <https://www.rfc-editor.org/rfc/rfc8705#section-3.1>
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OAuth2TokenConfirmation {
+    /// The base64url encoded SHA-256 thumbprint of the DPoP public key.
+    #[serde(default)]
+    pub jkt: Option<String>,
+    /// The base64url encoded SHA-256 thumbprint of the tls client certificate.
+    #[serde(default, rename = "x5t#S256")]
+    pub x5t_s256: Option<String>,
+}
+
+/// The acting party of a token issued by a token exchange.
+/// ref <https://www.rfc-editor.org/rfc/rfc8693#section-4.1>
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OAuth2TokenActor {
+    /// The client_id of the client that exchanged the token.
+    pub sub: String,
 }
 
 /// The response for an access token
@@ -248,6 +305,8 @@ pub struct AccessTokenResponse {
     pub scope: BTreeSet<String>,
     /// If the `openid` scope was requested, an `id_token` may be present in the response.
     pub id_token: Option<String>,
+    /// The type of the issued token, which is only present in response to a token exchange.
+    pub issued_token_type: Option<String>,
 }
 
 /// Access token types, per [IANA Registry - OAuth Access Token Types](https://www.iana.org/assignments/oauth-parameters/oauth-parameters.xhtml#token-types)
@@ -315,6 +374,8 @@ pub struct AccessTokenIntrospectResponse {
     pub aud: Option<String>,
     pub iss: Option<String>,
     pub jti: Option<String>,
+    /// The key the token is bound to, if it is a DPoP token.
+    pub cnf: Option<OAuth2TokenConfirmation>,
 }
 
 impl AccessTokenIntrospectResponse {
@@ -332,6 +393,7 @@ impl AccessTokenIntrospectResponse {
             aud: None,
             iss: None,
             jti: None,
+            cnf: None,
         }
     }
 }
@@ -403,6 +465,19 @@ pub enum TokenEndpointAuthMethod {
     ClientSecretBasic,
     ClientSecretJwt,
     PrivateKeyJwt,
+    /// The client is public and does not authenticate to the token endpoint.
+    None,
+}
+
+/// The kind of application a client is, which determines the redirect uris it may use.
+/// Ref <https://openid.net/specs/openid-connect-registration-1_0.html#ClientMetadata>
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplicationType {
+    #[default]
+    Web,
+    /// A native or mobile application that can't hold a client secret.
+    Native,
 }
 
 fn token_endpoint_auth_methods_supported_default() -> Vec<TokenEndpointAuthMethod> {
@@ -539,6 +614,12 @@ pub struct OidcDiscoveryResponse {
     pub introspection_endpoint_auth_methods_supported: Vec<TokenEndpointAuthMethod>,
     pub introspection_endpoint_auth_signing_alg_values_supported: Option<Vec<IdTokenSignAlg>>,
 
+    /// Ref <https://www.rfc-editor.org/rfc/rfc9449#section-5.1>
+    pub dpop_signing_alg_values_supported: Option<Vec<IdTokenSignAlg>>,
+
+    /// Ref <https://www.rfc-editor.org/rfc/rfc8705#section-3.3>
+    pub tls_client_certificate_bound_access_tokens: Option<bool>,
+
     /// Ref <https://www.rfc-editor.org/rfc/rfc8628#section-4>
     pub device_authorization_endpoint: Option<Url>,
 }
@@ -585,10 +666,53 @@ pub struct Oauth2Rfc8414MetadataResponse {
     pub introspection_endpoint_auth_methods_supported: Vec<TokenEndpointAuthMethod>,
     pub introspection_endpoint_auth_signing_alg_values_supported: Option<Vec<IdTokenSignAlg>>,
 
+    // RFC9449
+    pub dpop_signing_alg_values_supported: Option<Vec<IdTokenSignAlg>>,
+
+    // RFC8705
+    pub tls_client_certificate_bound_access_tokens: Option<bool>,
+
     // RFC7636
     pub code_challenge_methods_supported: Vec<PkceAlg>,
 }
 
+/// Client metadata submitted to the dynamic client registration endpoint.
+/// Ref <https://www.rfc-editor.org/rfc/rfc7591#section-2>
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClientRegistrationRequest {
+    pub redirect_uris: Vec<Url>,
+    /// If not provided this defaults to `client_secret_basic`.
+    pub token_endpoint_auth_method: Option<TokenEndpointAuthMethod>,
+    pub client_name: Option<String>,
+    /// If not provided this defaults to `web`. `native` clients must not use a client secret.
+    pub application_type: Option<ApplicationType>,
+    /// The scopes the client may request. If not provided the client is granted no scopes.
+    #[serde_as(as = "Option<StringWithSeparator::<SpaceSeparator, String>>")]
+    pub scope: Option<BTreeSet<String>>,
+}
+
+/// The response to a successful dynamic client registration.
+/// Ref <https://www.rfc-editor.org/rfc/rfc7591#section-3.2.1>
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClientRegistrationResponse {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub client_id_issued_at: i64,
+    /// Required if a client_secret is issued. A value of 0 means the secret does not expire.
+    pub client_secret_expires_at: Option<i64>,
+    pub redirect_uris: Vec<Url>,
+    pub token_endpoint_auth_method: TokenEndpointAuthMethod,
+    pub client_name: String,
+    pub application_type: ApplicationType,
+    /// The scopes that were granted to the client.
+    #[serde_as(as = "Option<StringWithSeparator::<SpaceSeparator, String>>")]
+    pub scope: Option<BTreeSet<String>>,
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ErrorResponse {
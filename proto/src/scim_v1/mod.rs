@@ -31,6 +31,7 @@ pub use scim_proto::prelude::*;
 pub use serde_json::Value as JsonValue;
 
 pub mod client;
+pub mod provision;
 pub mod server;
 mod synch;
 
@@ -0,0 +1,248 @@
+//! Resources for inbound SCIM 2.0 provisioning. Unlike the rest of this module these
+//! follow the core user and group schemas of RFC 7643 rather than Kanidm's attribute
+//! names, so that they can be sent by generic SCIM clients such as an external IdP or
+//! HR system.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use serde_with::skip_serializing_none;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use scim_proto::constants::{
+    SCIM_SCHEMA_GROUP, SCIM_SCHEMA_LIST_RESPONSE, SCIM_SCHEMA_PATCH_OP, SCIM_SCHEMA_USER,
+};
+
+pub use scim_proto::filter::{AttrPath, ScimComplexFilter, ScimFilter};
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimProvisionName {
+    pub formatted: Option<String>,
+    pub family_name: Option<String>,
+    pub given_name: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimProvisionEmail {
+    pub value: String,
+    #[serde(default)]
+    pub primary: bool,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimProvisionMember {
+    pub value: Uuid,
+    pub display: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimProvisionUser {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    /// Assigned by the server, ignored if sent by the client.
+    pub id: Option<Uuid>,
+    pub external_id: Option<String>,
+    pub user_name: String,
+    pub name: Option<ScimProvisionName>,
+    pub display_name: Option<String>,
+    pub active: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<ScimProvisionEmail>,
+    /// The groups this user is a member of, directly or indirectly. This is read only,
+    /// membership is managed through the group resource.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<ScimProvisionMember>,
+}
+
+impl ScimProvisionUser {
+    pub fn new(user_name: String) -> Self {
+        ScimProvisionUser {
+            schemas: vec![SCIM_SCHEMA_USER.to_string()],
+            id: None,
+            external_id: None,
+            user_name,
+            name: None,
+            display_name: None,
+            active: None,
+            emails: Vec::with_capacity(0),
+            groups: Vec::with_capacity(0),
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimProvisionGroup {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    /// Assigned by the server, ignored if sent by the client.
+    pub id: Option<Uuid>,
+    pub external_id: Option<String>,
+    pub display_name: String,
+    #[serde(default)]
+    pub members: Vec<ScimProvisionMember>,
+}
+
+impl ScimProvisionGroup {
+    pub fn new(display_name: String) -> Self {
+        ScimProvisionGroup {
+            schemas: vec![SCIM_SCHEMA_GROUP.to_string()],
+            id: None,
+            external_id: None,
+            display_name,
+            members: Vec::with_capacity(0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[aliases(
+    ScimProvisionUserListResponse = ScimProvisionListResponse<ScimProvisionUser>,
+    ScimProvisionGroupListResponse = ScimProvisionListResponse<ScimProvisionGroup>
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimProvisionListResponse<T> {
+    pub schemas: Vec<String>,
+    pub total_results: usize,
+    pub start_index: usize,
+    pub items_per_page: usize,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<T>,
+}
+
+impl<T> ScimProvisionListResponse<T> {
+    pub fn new(resources: Vec<T>, total_results: usize, start_index: usize) -> Self {
+        ScimProvisionListResponse {
+            schemas: vec![SCIM_SCHEMA_LIST_RESPONSE.to_string()],
+            total_results,
+            start_index,
+            items_per_page: resources.len(),
+            resources,
+        }
+    }
+}
+
+/// Query parameters for listing resources. Pagination follows RFC 7644 where
+/// `start_index` is 1-based.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimProvisionListQuery {
+    pub filter: Option<String>,
+    pub start_index: Option<usize>,
+    pub count: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(try_from = "String", into = "String")]
+pub enum ScimPatchOp {
+    Add,
+    Remove,
+    Replace,
+}
+
+impl TryFrom<String> for ScimPatchOp {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        // Some clients send these capitalised, even though the RFC specifies lowercase.
+        match value.to_lowercase().as_str() {
+            "add" => Ok(ScimPatchOp::Add),
+            "remove" => Ok(ScimPatchOp::Remove),
+            "replace" => Ok(ScimPatchOp::Replace),
+            _ => Err(format!("invalid patch operation {}", value)),
+        }
+    }
+}
+
+impl From<ScimPatchOp> for String {
+    fn from(value: ScimPatchOp) -> Self {
+        match value {
+            ScimPatchOp::Add => "add",
+            ScimPatchOp::Remove => "remove",
+            ScimPatchOp::Replace => "replace",
+        }
+        .to_string()
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ScimPatchOperation {
+    pub op: ScimPatchOp,
+    pub path: Option<String>,
+    pub value: Option<JsonValue>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ScimPatchRequest {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimPatchOperation>,
+}
+
+impl ScimPatchRequest {
+    pub fn new(operations: Vec<ScimPatchOperation>) -> Self {
+        ScimPatchRequest {
+            schemas: vec![SCIM_SCHEMA_PATCH_OP.to_string()],
+            operations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scim_provision_patch_parse() {
+        // As sent by Azure AD, note the capitalised op.
+        let patch: ScimPatchRequest = serde_json::from_str(
+            r#"{
+                "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+                "Operations": [
+                    { "op": "Replace", "path": "active", "value": "False" },
+                    { "op": "remove", "path": "members[value eq \"2d0a9e7c-cc08-4ca2-8d7f-114f9abcfc8a\"]" }
+                ]
+            }"#,
+        )
+        .expect("Failed to parse patch request");
+
+        assert_eq!(patch.operations.len(), 2);
+        assert_eq!(patch.operations[0].op, ScimPatchOp::Replace);
+        assert_eq!(patch.operations[1].op, ScimPatchOp::Remove);
+        assert!(patch.operations[1].value.is_none());
+    }
+
+    #[test]
+    fn scim_provision_user_parse() {
+        let user: ScimProvisionUser = serde_json::from_str(
+            r#"{
+                "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User"],
+                "userName": "bjensen",
+                "externalId": "701984",
+                "name": { "givenName": "Barbara", "familyName": "Jensen" },
+                "displayName": "Babs Jensen",
+                "active": true,
+                "emails": [{ "value": "bjensen@example.com", "type": "work", "primary": true }]
+            }"#,
+        )
+        .expect("Failed to parse user");
+
+        assert_eq!(user.user_name, "bjensen");
+        assert_eq!(user.emails.len(), 1);
+        assert!(user.emails[0].primary);
+        assert!(user.id.is_none());
+    }
+}
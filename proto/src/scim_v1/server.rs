@@ -268,6 +268,10 @@ pub struct ScimPerson {
     pub mails: Vec<ScimMail>,
     pub managed_by: Option<ScimReference>,
     pub groups: Vec<ScimReference>,
+    #[serde_as(as = "Option<Rfc3339>")]
+    pub account_valid_from: Option<OffsetDateTime>,
+    #[serde_as(as = "Option<Rfc3339>")]
+    pub account_expire: Option<OffsetDateTime>,
 }
 
 impl TryFrom<ScimEntryKanidm> for ScimPerson {
@@ -336,6 +340,23 @@ impl TryFrom<ScimEntryKanidm> for ScimPerson {
                 _ => None,
             });
 
+        let account_valid_from = scim_entry
+            .attrs
+            .get(&Attribute::AccountValidFrom)
+            .and_then(|v| match v {
+                ScimValueKanidm::DateTime(odt) => Some(*odt),
+                _ => None,
+            });
+
+        let account_expire =
+            scim_entry
+                .attrs
+                .get(&Attribute::AccountExpire)
+                .and_then(|v| match v {
+                    ScimValueKanidm::DateTime(odt) => Some(*odt),
+                    _ => None,
+                });
+
         Ok(ScimPerson {
             uuid,
             name,
@@ -345,6 +366,76 @@ impl TryFrom<ScimEntryKanidm> for ScimPerson {
             mails,
             managed_by,
             groups,
+            account_valid_from,
+            account_expire,
+        })
+    }
+}
+
+impl ScimPerson {
+    /// If the account is within its validity window at this time, and so may authenticate.
+    pub fn is_valid_at(&self, ct: OffsetDateTime) -> bool {
+        self.account_valid_from.map_or(true, |vf| vf <= ct)
+            && self.account_expire.map_or(true, |exp| ct < exp)
+    }
+}
+
+#[serde_as]
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ScimGroup {
+    pub uuid: Uuid,
+    pub name: String,
+    pub spn: String,
+    pub description: Option<String>,
+    pub members: Vec<ScimReference>,
+}
+
+impl TryFrom<ScimEntryKanidm> for ScimGroup {
+    type Error = ();
+
+    fn try_from(scim_entry: ScimEntryKanidm) -> Result<Self, Self::Error> {
+        let uuid = scim_entry.header.id;
+        let name = scim_entry
+            .attrs
+            .get(&Attribute::Name)
+            .and_then(|v| match v {
+                ScimValueKanidm::String(s) => Some(s.clone()),
+                _ => None,
+            })
+            .ok_or(())?;
+
+        let spn = scim_entry
+            .attrs
+            .get(&Attribute::Spn)
+            .and_then(|v| match v {
+                ScimValueKanidm::String(s) => Some(s.clone()),
+                _ => None,
+            })
+            .ok_or(())?;
+
+        let description = scim_entry
+            .attrs
+            .get(&Attribute::Description)
+            .and_then(|v| match v {
+                ScimValueKanidm::String(s) => Some(s.clone()),
+                _ => None,
+            });
+
+        let members = scim_entry
+            .attrs
+            .get(&Attribute::Member)
+            .and_then(|v| match v {
+                ScimValueKanidm::EntryReferences(v) => Some(v.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        Ok(ScimGroup {
+            uuid,
+            name,
+            spn,
+            description,
+            members,
         })
     }
 }
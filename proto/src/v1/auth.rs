@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
+use serde_with::base64::{Base64, UrlSafe};
+use serde_with::{formats, serde_as};
 use std::cmp::Ordering;
 use std::fmt;
+use url::Url;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -51,6 +54,9 @@ pub enum AuthState {
     Denied(String),
     /// Everything is good, your bearer token has been issued and is within.
     Success(String),
+    /// Your password has expired. No bearer token is issued until it is changed, and the
+    /// token within can be exchanged for a credential update session to do so.
+    CredentialUpdateRequired(String),
 }
 
 /// The credential challenge provided by a user.
@@ -64,6 +70,14 @@ pub enum AuthCredential {
     BackupCode(String),
     // Should this just be discoverable?
     Passkey(Box<PublicKeyCredential>),
+    /// The token from a link that was sent to the mail address of the account.
+    EmailLink(String),
+    /// The state of a login with an upstream identity provider. This only succeeds once the
+    /// server has verified the identity that the provider returned for this state.
+    Federated(String),
+    /// A jwt signed by a key registered to the service account, which contains the nonce
+    /// issued for this authentication.
+    JwtAssertion(String),
 }
 
 impl fmt::Debug for AuthCredential {
@@ -75,6 +89,9 @@ impl fmt::Debug for AuthCredential {
             AuthCredential::SecurityKey(_) => write!(fmt, "SecurityKey(_)"),
             AuthCredential::BackupCode(_) => write!(fmt, "BackupCode(_)"),
             AuthCredential::Passkey(_) => write!(fmt, "Passkey(_)"),
+            AuthCredential::EmailLink(_) => write!(fmt, "EmailLink(_)"),
+            AuthCredential::Federated(_) => write!(fmt, "Federated(_)"),
+            AuthCredential::JwtAssertion(_) => write!(fmt, "JwtAssertion(_)"),
         }
     }
 }
@@ -85,12 +102,15 @@ impl fmt::Debug for AuthCredential {
 pub enum AuthMech {
     Anonymous,
     Password,
+    EmailLink,
+    Federated,
     PasswordBackupCode,
     // Now represents TOTP.
     #[serde(rename = "passwordmfa")]
     PasswordTotp,
     PasswordSecurityKey,
     Passkey,
+    JwtAssertion,
 }
 
 impl AuthMech {
@@ -98,10 +118,13 @@ impl AuthMech {
         match self {
             AuthMech::Anonymous => "anonymous",
             AuthMech::Password => "password",
+            AuthMech::EmailLink => "emaillink",
+            AuthMech::Federated => "federated",
             AuthMech::PasswordTotp => "passwordmfa",
             AuthMech::PasswordBackupCode => "passwordbackupcode",
             AuthMech::PasswordSecurityKey => "passwordsecuritykey",
             AuthMech::Passkey => "passkey",
+            AuthMech::JwtAssertion => "jwtassertion",
         }
     }
 }
@@ -117,10 +140,13 @@ impl fmt::Display for AuthMech {
         match self {
             AuthMech::Anonymous => write!(f, "Anonymous (no credentials)"),
             AuthMech::Password => write!(f, "Password"),
+            AuthMech::EmailLink => write!(f, "Email Link"),
+            AuthMech::Federated => write!(f, "Upstream Identity Provider"),
             AuthMech::PasswordTotp => write!(f, "TOTP and Password"),
             AuthMech::PasswordBackupCode => write!(f, "Backup Code and Password"),
             AuthMech::PasswordSecurityKey => write!(f, "Security Key and Password"),
             AuthMech::Passkey => write!(f, "Passkey"),
+            AuthMech::JwtAssertion => write!(f, "Signed JWT Assertion"),
         }
     }
 }
@@ -153,6 +179,23 @@ pub enum AuthAllowed {
     Totp,
     SecurityKey(RequestChallengeResponse),
     Passkey(RequestChallengeResponse),
+    /// A link has been sent to the mail address of the account, and the token it contains
+    /// must be provided.
+    EmailLink,
+    /// Login with one of these upstream identity providers.
+    Federated(Vec<FederatedProvider>),
+    /// A jwt signed by a registered key must be provided, containing this nonce.
+    JwtAssertion(String),
+}
+
+/// An upstream identity provider that the account may login with.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct FederatedProvider {
+    pub name: String,
+    pub displayname: String,
+    /// Where the browser is sent to login with the provider.
+    #[schema(value_type = String)]
+    pub authorise_url: Url,
 }
 
 impl PartialEq for AuthAllowed {
@@ -170,6 +213,9 @@ impl From<&AuthAllowed> for u8 {
             AuthAllowed::Totp => 3,
             AuthAllowed::Passkey(_) => 4,
             AuthAllowed::SecurityKey(_) => 5,
+            AuthAllowed::EmailLink => 6,
+            AuthAllowed::Federated(_) => 7,
+            AuthAllowed::JwtAssertion(_) => 8,
         }
     }
 }
@@ -199,6 +245,9 @@ impl fmt::Display for AuthAllowed {
             AuthAllowed::Totp => write!(f, "TOTP"),
             AuthAllowed::SecurityKey(_) => write!(f, "Security Token"),
             AuthAllowed::Passkey(_) => write!(f, "Passkey"),
+            AuthAllowed::EmailLink => write!(f, "Email Link"),
+            AuthAllowed::Federated(_) => write!(f, "Upstream Identity Provider"),
+            AuthAllowed::JwtAssertion(_) => write!(f, "Signed JWT Assertion"),
         }
     }
 }
@@ -208,3 +257,37 @@ pub struct AuthResponse {
     pub sessionid: Uuid,
     pub state: AuthState,
 }
+
+/// A challenge that must be signed by the break glass key to authenticate the admin account.
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BreakGlassChallenge {
+    pub id: Uuid,
+    #[serde_as(as = "Base64<UrlSafe, formats::Unpadded>")]
+    #[schema(value_type = String)]
+    pub challenge: Vec<u8>,
+}
+
+/// The response to a [BreakGlassChallenge], containing the DER encoded ECDSA signature of
+/// the challenge.
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BreakGlassRequest {
+    pub id: Uuid,
+    #[serde_as(as = "Base64<UrlSafe, formats::Unpadded>")]
+    #[schema(value_type = String)]
+    pub signature: Vec<u8>,
+}
+
+/// A recovery code of an emergency account, that was generated offline.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BreakGlassCodeRequest {
+    pub account: String,
+    pub code: String,
+}
+
+/// A short lived session token for the admin account, or an emergency account.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BreakGlassResponse {
+    pub token: String,
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Request that members are added to a group until a point in time, after which their
+/// membership lapses.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct GroupTemporaryMembers {
+    pub members: Vec<String>,
+    #[serde(with = "time::serde::timestamp")]
+    pub valid_until: OffsetDateTime,
+}
+
+/// The time that the membership of a member of a group lapses. This is stored on the group
+/// in the form `member_uuid=valid_until`, where the time is an RFC3339 time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupMemberValidUntil {
+    pub member: Uuid,
+    pub valid_until: OffsetDateTime,
+}
+
+impl FromStr for GroupMemberValidUntil {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (member, valid_until) = s.split_once('=').ok_or(())?;
+
+        let member = Uuid::parse_str(member).map_err(|_| ())?;
+
+        let valid_until = OffsetDateTime::parse(valid_until, &Rfc3339)
+            .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+            .map_err(|_| ())?;
+
+        Ok(GroupMemberValidUntil {
+            member,
+            valid_until,
+        })
+    }
+}
+
+impl fmt::Display for GroupMemberValidUntil {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let valid_until = self.valid_until.format(&Rfc3339).map_err(|_| fmt::Error)?;
+        write!(f, "{}={}", self.member, valid_until)
+    }
+}
+
+/// The state of a request to join a group.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupMembershipRequestStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+impl fmt::Display for GroupMembershipRequestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupMembershipRequestStatus::Pending => f.write_str("pending"),
+            GroupMembershipRequestStatus::Approved => f.write_str("approved"),
+            GroupMembershipRequestStatus::Denied => f.write_str("denied"),
+        }
+    }
+}
+
+impl FromStr for GroupMembershipRequestStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(GroupMembershipRequestStatus::Pending),
+            "approved" => Ok(GroupMembershipRequestStatus::Approved),
+            "denied" => Ok(GroupMembershipRequestStatus::Denied),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Request to join a group that accepts membership requests.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct GroupMembershipRequestCreate {
+    /// Why the requester needs to be a member of the group, shown to the approvers.
+    pub justification: Option<String>,
+}
+
+/// Approve a request to join a group. If valid until is set the membership lapses at that time.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct GroupMembershipRequestApprove {
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub valid_until: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct GroupMembershipRequest {
+    pub uuid: Uuid,
+    /// The spn of the group that was requested.
+    pub group: String,
+    /// The spn of the account that made the request.
+    pub requester: String,
+    pub justification: Option<String>,
+    pub status: GroupMembershipRequestStatus,
+    /// The spn of the account that approved or denied the request.
+    pub decided_by: Option<String>,
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub valid_until: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::timestamp")]
+    pub requested_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct GroupMembershipRequests {
+    /// Pending requests to join groups that the caller is able to approve.
+    pub awaiting_approval: Vec<GroupMembershipRequest>,
+    /// The requests that the caller has made.
+    pub requested: Vec<GroupMembershipRequest>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GroupMemberValidUntil, GroupMembershipRequestStatus};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_group_member_valid_until() {
+        let valid_until = GroupMemberValidUntil::from_str(
+            "00000000-0000-0000-0000-000000000000=2030-01-01T10:00:00+10:00",
+        )
+        .expect("Invalid group member valid until");
+        assert_eq!(valid_until.member, uuid::Uuid::nil());
+        // The time is always shown in UTC.
+        assert_eq!(
+            valid_until.to_string(),
+            "00000000-0000-0000-0000-000000000000=2030-01-01T00:00:00Z"
+        );
+
+        assert!(GroupMemberValidUntil::from_str("2030-01-01T00:00:00Z").is_err());
+        assert!(GroupMemberValidUntil::from_str("admin=2030-01-01T00:00:00Z").is_err());
+        assert!(
+            GroupMemberValidUntil::from_str("00000000-0000-0000-0000-000000000000=tomorrow")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_group_membership_request_status() {
+        for status in [
+            GroupMembershipRequestStatus::Pending,
+            GroupMembershipRequestStatus::Approved,
+            GroupMembershipRequestStatus::Denied,
+        ] {
+            assert_eq!(
+                GroupMembershipRequestStatus::from_str(&status.to_string()),
+                Ok(status)
+            );
+        }
+        assert!(GroupMembershipRequestStatus::from_str("cancelled").is_err());
+    }
+}
@@ -5,6 +5,7 @@
 
 #![allow(non_upper_case_globals)]
 
+use crate::internal::ApiTokenPermission;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fmt;
@@ -13,9 +14,13 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 
 mod auth;
+mod group;
+mod state;
 mod unix;
 
 pub use self::auth::*;
+pub use self::group::*;
+pub use self::state::*;
 pub use self::unix::*;
 
 /// The type of Account in use.
@@ -97,6 +102,53 @@ impl fmt::Display for UatStatus {
     }
 }
 
+/// The reason the server revoked an OAuth2 session.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Oauth2SessionRevokeReason {
+    RefreshTokenReuse,
+}
+
+impl fmt::Display for Oauth2SessionRevokeReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Oauth2SessionRevokeReason::RefreshTokenReuse => write!(f, "refresh token reuse"),
+        }
+    }
+}
+
+/// The status of an OAuth2 session. When held by a resource server these are sessions
+/// issued through the client credentials grant.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub struct Oauth2SessionStatus {
+    pub session_id: Uuid,
+    pub rs_uuid: Uuid,
+    pub parent_session_id: Option<Uuid>,
+    pub state: UatStatusState,
+    #[serde(with = "time::serde::timestamp")]
+    pub issued_at: time::OffsetDateTime,
+    #[serde(default)]
+    pub revoke_reason: Option<Oauth2SessionRevokeReason>,
+}
+
+impl fmt::Display for Oauth2SessionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "session_id: {}", self.session_id)?;
+        writeln!(f, "rs_uuid: {}", self.rs_uuid)?;
+        match &self.parent_session_id {
+            Some(parent) => writeln!(f, "parent_session_id: {}", parent)?,
+            None => writeln!(f, "grant: client credentials")?,
+        }
+        writeln!(f, "state: {}", self.state)?;
+        writeln!(f, "issued_at: {}", self.issued_at)?;
+        if let Some(reason) = &self.revoke_reason {
+            writeln!(f, "revoke_reason: {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
 /// A request to generate a new API token for a service account
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "lowercase")]
@@ -105,6 +157,19 @@ pub struct ApiTokenGenerate {
     #[serde(with = "time::serde::timestamp::option")]
     pub expiry: Option<time::OffsetDateTime>,
     pub read_write: bool,
+    /// Restrict the token to only modify what these permissions allow. This requires
+    /// `read_write`.
+    #[serde(default)]
+    pub permissions: Vec<ApiTokenPermission>,
+}
+
+/// A request to register a public key that may sign jwt assertions for a service account
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub struct JwtPublicKeyAdd {
+    pub label: String,
+    /// An ECDSA P-256 public key in PEM format.
+    pub public_key: String,
 }
 
 /* ===== low level proto types ===== */
@@ -148,3 +213,27 @@ impl SingleStringRequest {
         SingleStringRequest { value: s }
     }
 }
+
+/// A request for the entries that have changed since a previous request.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ChangesQuery {
+    /// The cursor from a previous [ChangesResponse]. If absent, only the current cursor is
+    /// returned.
+    pub cursor: Option<String>,
+}
+
+/// The entries that have changed since the provided cursor. Only changes to entries that
+/// the requester is able to read are reported as changed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default, ToSchema)]
+pub struct ChangesResponse {
+    /// An opaque cursor to provide in the next request.
+    pub cursor: String,
+    /// The cursor was absent, or too old for the changes since it to be known. Any state
+    /// derived from previous responses must be discarded.
+    pub reset: bool,
+    /// Entries that have been created or modified.
+    pub changed: Vec<Uuid>,
+    /// Entries that have been deleted. Only those who can read the recycle bin are told which
+    /// entries were deleted, others are told to reset instead.
+    pub removed: Vec<Uuid>,
+}
@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use url::Url;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A declarative description of the configuration of a Kanidm server. Only the entries and
+/// attributes that are described are managed, anything else on the server is left unchanged.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DeclarativeState {
+    #[serde(default)]
+    pub groups: Vec<GroupState>,
+    #[serde(default)]
+    pub oauth2_clients: Vec<Oauth2ClientState>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GroupState {
+    pub name: String,
+    pub description: Option<String>,
+    /// The names or spns of the members. This replaces all existing members of the group.
+    pub members: Option<Vec<String>>,
+    pub entry_managed_by: Option<String>,
+    pub mail: Option<Vec<String>>,
+    /// Setting an account policy enables account policy on the group.
+    pub account_policy: Option<AccountPolicyState>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AccountPolicyState {
+    /// The maximum length of an authentication session in seconds.
+    pub authsession_expiry: Option<u32>,
+    /// The length of a privileged session in seconds.
+    pub privilege_expiry: Option<u32>,
+    /// One of `any`, `mfa`, `passkey` or `attested_passkey`.
+    pub credential_type_minimum: Option<String>,
+    pub password_minimum_length: Option<u32>,
+    pub password_history_count: Option<u32>,
+    pub allow_primary_cred_fallback: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Oauth2ClientState {
+    pub name: String,
+    pub displayname: String,
+    /// The landing page of the application.
+    pub origin_landing: Url,
+    /// If the client is a public client that can't keep a secret, such as a single page app.
+    /// This only has an effect when the client is created.
+    #[serde(default)]
+    pub public: bool,
+    pub redirect_urls: Option<Vec<Url>>,
+    /// The scopes that members of each group are granted. This replaces all existing scope maps.
+    pub scope_maps: Option<BTreeMap<String, BTreeSet<String>>>,
+    /// The supplementary scopes that members of each group are granted. This replaces all
+    /// existing supplementary scope maps.
+    pub sup_scope_maps: Option<BTreeMap<String, BTreeSet<String>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StateEntryKind {
+    Group,
+    Oauth2Client,
+}
+
+impl fmt::Display for StateEntryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateEntryKind::Group => f.write_str("group"),
+            StateEntryKind::Oauth2Client => f.write_str("oauth2 client"),
+        }
+    }
+}
+
+/// A modification that is required for the server to match a declarative state.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "change")]
+pub enum StateChange {
+    CreateGroup {
+        name: String,
+    },
+    CreateOauth2Client {
+        name: String,
+        displayname: String,
+        origin_landing: Url,
+        public: bool,
+    },
+    EnableAccountPolicy {
+        group: String,
+    },
+    SetAttr {
+        kind: StateEntryKind,
+        name: String,
+        attr: String,
+        values: Vec<String>,
+    },
+    PurgeAttr {
+        kind: StateEntryKind,
+        name: String,
+        attr: String,
+    },
+    SetScopeMap {
+        client: String,
+        group: String,
+        scopes: BTreeSet<String>,
+        supplementary: bool,
+    },
+    DeleteScopeMap {
+        client: String,
+        group: String,
+        supplementary: bool,
+    },
+}
+
+impl fmt::Display for StateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateChange::CreateGroup { name } => write!(f, "create group {}", name),
+            StateChange::CreateOauth2Client { name, public, .. } => {
+                let client_type = if *public { "public" } else { "basic" };
+                write!(f, "create {} oauth2 client {}", client_type, name)
+            }
+            StateChange::EnableAccountPolicy { group } => {
+                write!(f, "enable account policy on group {}", group)
+            }
+            StateChange::SetAttr {
+                kind,
+                name,
+                attr,
+                values,
+            } => write!(f, "set {} {} {} = {:?}", kind, name, attr, values),
+            StateChange::PurgeAttr { kind, name, attr } => {
+                write!(f, "purge {} {} {}", kind, name, attr)
+            }
+            StateChange::SetScopeMap {
+                client,
+                group,
+                scopes,
+                supplementary,
+            } => {
+                let map = if *supplementary {
+                    "sup scope map"
+                } else {
+                    "scope map"
+                };
+                write!(
+                    f,
+                    "set oauth2 client {} {} {} = {:?}",
+                    client, map, group, scopes
+                )
+            }
+            StateChange::DeleteScopeMap {
+                client,
+                group,
+                supplementary,
+            } => {
+                let map = if *supplementary {
+                    "sup scope map"
+                } else {
+                    "scope map"
+                };
+                write!(f, "delete oauth2 client {} {} {}", client, map, group)
+            }
+        }
+    }
+}
+
+/// The result of creating or updating an entry to match a description.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct EntryApplyResponse {
+    pub uuid: Uuid,
+    /// If the entry did not exist and was created.
+    pub created: bool,
+    /// The attributes whose values were changed.
+    pub modified: Vec<String>,
+    /// The version of the entry after the change, to be sent as If-Match in a later change.
+    /// In a dry run this is the current version, or none if the entry would be created.
+    pub version: Option<String>,
+    /// If this describes what would change, without having changed anything.
+    pub dry_run: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeclarativeState;
+
+    #[test]
+    fn test_declarative_state_deserialise() {
+        let state: DeclarativeState = serde_json::from_str(
+            r#"{
+                "groups": [
+                    { "name": "eng", "members": ["alice"], "account_policy": { "privilege_expiry": 900 } }
+                ],
+                "oauth2_clients": [
+                    {
+                        "name": "wiki",
+                        "displayname": "Wiki",
+                        "origin_landing": "https://wiki.example.com/",
+                        "scope_maps": { "eng": ["openid", "email"] }
+                    }
+                ]
+            }"#,
+        )
+        .expect("Invalid state");
+
+        assert_eq!(state.groups.len(), 1);
+        assert!(state.groups[0].description.is_none());
+        assert_eq!(
+            state.groups[0]
+                .account_policy
+                .as_ref()
+                .and_then(|ap| ap.privilege_expiry),
+            Some(900)
+        );
+        assert!(!state.oauth2_clients[0].public);
+
+        // Typos must not be silently ignored.
+        assert!(
+            serde_json::from_str::<DeclarativeState>(r#"{ "groups": [{ "nme": "eng" }] }"#)
+                .is_err()
+        );
+    }
+}
@@ -2,10 +2,14 @@ use serde::{Deserialize, Serialize};
 use sshkey_attest::proto::PublicKey as SshPublicKey;
 use sshkeys::{KeyType, KeyTypeKind, PublicKeyKind};
 use std::fmt;
+use std::str::FromStr;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use serde_with::skip_serializing_none;
+use serde_with::base64::Base64;
+use serde_with::{serde_as, skip_serializing_none};
 
 use crate::constants::{ATTR_GROUP, ATTR_LDAP_SSHPUBLICKEY};
 
@@ -72,8 +76,23 @@ pub struct UnixUserToken {
     pub gidnumber: u32,
     pub uuid: Uuid,
     pub shell: Option<String>,
+    /// The home directory name from the account policy, if one is set.
+    pub home_directory: Option<String>,
+    /// The name of the skeleton to create the home directory from.
+    pub home_skeleton: Option<String>,
+    /// The filesystem quota of the home directory in MiB.
+    pub home_quota: Option<u32>,
+    /// The gecos from the account policy, if one is set.
+    pub gecos: Option<String>,
     pub groups: Vec<UnixGroupToken>,
     pub sshkeys: Vec<SshPublicKey>,
+    /// The host based access control rules that apply to this account. This is `None`
+    /// when no rules are defined in the domain, in which case hosts decide access locally.
+    pub hbac: Option<UnixHbacPolicy>,
+    /// The hosts that this account may log in to, resolved from the login host tags of its
+    /// account policy. This is `None` when logins are not restricted to tagged hosts.
+    #[serde(default)]
+    pub login_hosts: Option<Vec<String>>,
     // The default value of bool is false.
     #[serde(default)]
     pub valid: bool,
@@ -90,15 +109,101 @@ impl fmt::Display for UnixUserToken {
             Some(s) => writeln!(f, "shell: {}", s)?,
             None => writeln!(f, "shell: <none>")?,
         }
+        if let Some(home_directory) = &self.home_directory {
+            writeln!(f, "home_directory: {}", home_directory)?;
+        }
+        if let Some(gecos) = &self.gecos {
+            writeln!(f, "gecos: {}", gecos)?;
+        }
         self.sshkeys
             .iter()
             .try_for_each(|s| writeln!(f, "{}: {}", ATTR_LDAP_SSHPUBLICKEY, s))?;
+        if let Some(hbac) = &self.hbac {
+            hbac.allow
+                .iter()
+                .try_for_each(|r| writeln!(f, "hbac allow: {}", r))?;
+            hbac.deny
+                .iter()
+                .try_for_each(|r| writeln!(f, "hbac deny: {}", r))?;
+        }
+        if let Some(login_hosts) = &self.login_hosts {
+            writeln!(f, "login hosts: {}", login_hosts.join(" "))?;
+        }
         self.groups
             .iter()
             .try_for_each(|g| writeln!(f, "{}: {}", ATTR_GROUP, g))
     }
 }
 
+/// A host based access control rule. An empty list of hosts or services matches
+/// any host or service.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct UnixHbacRule {
+    pub name: String,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    #[serde(default)]
+    pub services: Vec<String>,
+}
+
+impl UnixHbacRule {
+    /// Check if this rule applies to the named host and pam service. When the service
+    /// is not known, `unknown_service` is the result of the service comparison.
+    fn matches(&self, host: &str, service: Option<&str>, unknown_service: bool) -> bool {
+        let host_match =
+            self.hosts.is_empty() || self.hosts.iter().any(|h| h.eq_ignore_ascii_case(host));
+
+        let service_match = self.services.is_empty()
+            || service
+                .map(|service| {
+                    self.services
+                        .iter()
+                        .any(|s| s.eq_ignore_ascii_case(service))
+                })
+                .unwrap_or(unknown_service);
+
+        host_match && service_match
+    }
+}
+
+impl fmt::Display for UnixHbacRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[ name: {}, ", self.name)?;
+        if self.hosts.is_empty() {
+            write!(f, "hosts: <any>, ")?;
+        } else {
+            write!(f, "hosts: {}, ", self.hosts.join(" "))?;
+        }
+        if self.services.is_empty() {
+            write!(f, "services: <any> ]")
+        } else {
+            write!(f, "services: {} ]", self.services.join(" "))
+        }
+    }
+}
+
+/// The host based access control rules that apply to a unix user.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct UnixHbacPolicy {
+    #[serde(default)]
+    pub allow: Vec<UnixHbacRule>,
+    #[serde(default)]
+    pub deny: Vec<UnixHbacRule>,
+}
+
+impl UnixHbacPolicy {
+    /// Determine if the user may access the named host and pam service. Any matching
+    /// deny rule takes precedence over the allow rules, and if no allow rule matches then
+    /// access is denied. If the service is not known, rules that are limited to specific
+    /// services are treated as matching when they deny, and as not matching when they allow.
+    pub fn is_allowed(&self, host: &str, service: Option<&str>) -> bool {
+        if self.deny.iter().any(|r| r.matches(host, service, true)) {
+            return false;
+        }
+        self.allow.iter().any(|r| r.matches(host, service, false))
+    }
+}
+
 /// Request addition of unix attributes to an account
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[serde(deny_unknown_fields)]
@@ -109,3 +214,150 @@ pub struct AccountUnixExtend {
     #[serde(alias = "loginshell")]
     pub shell: Option<String>,
 }
+
+/// Request to enroll a machine as a host, exchanging the join token that was issued for the
+/// host for its credential.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HostEnrollRequest {
+    pub name: String,
+    pub join_token: String,
+}
+
+/// Request a Kerberos service ticket on behalf of an account that has logged in to the host.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KerberosTicketRequest {
+    pub account_id: String,
+    /// The service principal name, such as `nfs/files.example.com`.
+    pub service: String,
+}
+
+/// A Kerberos service ticket, and the session key that the client uses with it. The ticket is
+/// DER encoded and encrypted with the key of the service.
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct KerberosTicket {
+    pub realm: String,
+    pub client: String,
+    pub service: String,
+    pub enctype: i32,
+    #[serde_as(as = "Base64")]
+    #[schema(value_type = String)]
+    pub session_key: Vec<u8>,
+    pub auth_time: u64,
+    pub end_time: u64,
+    #[serde_as(as = "Base64")]
+    #[schema(value_type = String)]
+    pub ticket: Vec<u8>,
+}
+
+/// The keys of the service principals of a host, as an MIT keytab.
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct KerberosKeytab {
+    pub realm: String,
+    pub principals: Vec<String>,
+    #[serde_as(as = "Base64")]
+    #[schema(value_type = String)]
+    pub keytab: Vec<u8>,
+}
+
+/// The time that an ssh public key of an account expires, after which it is no longer
+/// distributed to clients. This is stored on the account in the form `label=expiry`, where
+/// the expiry is an RFC3339 time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshPublicKeyExpiry {
+    pub label: String,
+    pub expiry: OffsetDateTime,
+}
+
+impl FromStr for SshPublicKeyExpiry {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The time can't contain an '=', but the label may.
+        let (label, expiry) = s.rsplit_once('=').ok_or(())?;
+
+        if label.is_empty() {
+            return Err(());
+        }
+
+        let expiry = OffsetDateTime::parse(expiry, &Rfc3339)
+            .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+            .map_err(|_| ())?;
+
+        Ok(SshPublicKeyExpiry {
+            label: label.to_string(),
+            expiry,
+        })
+    }
+}
+
+impl fmt::Display for SshPublicKeyExpiry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expiry = self.expiry.format(&Rfc3339).map_err(|_| fmt::Error)?;
+        write!(f, "{}={}", self.label, expiry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SshPublicKeyExpiry, UnixHbacPolicy, UnixHbacRule};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_ssh_public_key_expiry() {
+        let expiry = SshPublicKeyExpiry::from_str("laptop=2030-01-01T10:00:00+10:00")
+            .expect("Invalid ssh public key expiry");
+        assert_eq!(expiry.label, "laptop");
+        // The expiry is always shown in UTC.
+        assert_eq!(expiry.to_string(), "laptop=2030-01-01T00:00:00Z");
+
+        let expiry = SshPublicKeyExpiry::from_str("a=b=2030-01-01T00:00:00Z")
+            .expect("Invalid ssh public key expiry");
+        assert_eq!(expiry.label, "a=b");
+
+        assert!(SshPublicKeyExpiry::from_str("laptop").is_err());
+        assert!(SshPublicKeyExpiry::from_str("=2030-01-01T00:00:00Z").is_err());
+        assert!(SshPublicKeyExpiry::from_str("laptop=tomorrow").is_err());
+    }
+
+    #[test]
+    fn test_unix_hbac_policy() {
+        let policy = UnixHbacPolicy {
+            allow: vec![
+                UnixHbacRule {
+                    name: "servers".to_string(),
+                    hosts: vec!["db1".to_string(), "db2".to_string()],
+                    services: vec![],
+                },
+                UnixHbacRule {
+                    name: "desktops".to_string(),
+                    hosts: vec![],
+                    services: vec!["gdm-password".to_string()],
+                },
+            ],
+            deny: vec![UnixHbacRule {
+                name: "no-ssh-db2".to_string(),
+                hosts: vec!["db2".to_string()],
+                services: vec!["sshd".to_string()],
+            }],
+        };
+
+        assert!(policy.is_allowed("db1", Some("sshd")));
+        assert!(policy.is_allowed("DB1", None));
+        assert!(policy.is_allowed("db2", Some("sudo")));
+        // A deny rule for a specific service applies when the service is unknown.
+        assert!(!policy.is_allowed("db2", None));
+        // Deny takes precedence.
+        assert!(!policy.is_allowed("db2", Some("sshd")));
+        assert!(policy.is_allowed("laptop", Some("gdm-password")));
+        assert!(!policy.is_allowed("laptop", Some("sshd")));
+        // An unknown service only matches rules for any service.
+        assert!(!policy.is_allowed("laptop", None));
+
+        // No allow rules means no access.
+        assert!(!UnixHbacPolicy::default().is_allowed("db1", Some("sshd")));
+    }
+}
@@ -15,7 +15,7 @@ from kanidm.types import AuthState, RadiusTokenResponse
 
 from .. import KanidmClient
 from . import radiusd
-from .utils import check_vlan
+from .utils import check_certificate_serial, check_vlan
 
 CONTAINER_CONFIG_FILE_PATH = "/data/radius.toml"
 
@@ -136,6 +136,13 @@ def authorize(
     secret = tok.secret
     uuid = tok.uuid
 
+    # With EAP-TLS the certificate must still be one that was issued to the account, so
+    # that removing the certificate revokes it.
+    cert_serial = dargs.get("TLS-Client-Cert-Serial", None)
+    if cert_serial is not None and not check_certificate_serial(cert_serial, tok.certificate_serials):
+        logging.info("User %s presented a certificate that is not valid for the account.", name)
+        return radiusd.RLM_MODULE_REJECT
+
     # Are they in the required group?
     req_sat = False
     required_groups = kanidm_client.config.radius_required_groups
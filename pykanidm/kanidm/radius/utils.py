@@ -1,6 +1,6 @@
 """ class utils """
 
-from typing import Optional
+from typing import List, Optional
 import logging
 import os
 
@@ -35,3 +35,20 @@ def check_vlan(
             return radius_group.vlan
     logging.debug("returning already set vlan: %s", acc)
     return acc
+
+
+def _normalise_serial(serial: str) -> str:
+    """strips separators, a hex prefix and leading zeros from a certificate serial"""
+    serial = serial.strip().lower().replace(":", "")
+    if serial.startswith("0x"):
+        serial = serial[2:]
+    return serial.lstrip("0")
+
+
+def check_certificate_serial(serial: str, certificate_serials: List[str]) -> bool:
+    """checks if the serial of a client certificate is one of the valid
+    certificates of the account"""
+    serial = _normalise_serial(serial)
+    if not serial:
+        return False
+    return any(serial == _normalise_serial(valid) for valid in certificate_serials)
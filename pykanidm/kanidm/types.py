@@ -111,6 +111,7 @@ class RadiusTokenResponse(BaseModel):
     uuid: str
 
     groups: List[RadiusTokenGroup]
+    certificate_serials: List[str] = []
     model_config = ConfigDict(arbitrary_types_allowed=True)
 
 
@@ -0,0 +1,17 @@
+"""tests the check_certificate_serial function"""
+
+from kanidm.radius.utils import check_certificate_serial
+
+
+def test_check_certificate_serial() -> None:
+    """serials are compared without separators, case or leading zeros"""
+
+    valid = ["1a2b3c", "00ff"]
+
+    assert check_certificate_serial("1A2B3C", valid)
+    assert check_certificate_serial("1a:2b:3c", valid)
+    assert check_certificate_serial("0x001a2b3c", valid)
+    assert check_certificate_serial("ff", valid)
+    assert not check_certificate_serial("1a2b3d", valid)
+    assert not check_certificate_serial("", valid)
+    assert not check_certificate_serial("1a2b3c", [])
@@ -0,0 +1,948 @@
+//! An ACME ([RFC 8555](https://www.rfc-editor.org/rfc/rfc8555)) client that obtains and renews
+//! the TLS certificate of this server. The account key and the issued certificate are stored in
+//! the key storage of this server, which is the source of truth. The certificate is written to
+//! the configured `tls_chain` and `tls_key` files, and the TLS acceptors are reloaded so that it
+//! is used without a restart.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Write;
+use std::net::SocketAddr;
+#[cfg(not(target_family = "windows"))]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path as FsPath;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use futures::pin_mut;
+use kanidm_lib_crypto::mtls::expires_within_days;
+use kanidmd_lib::idm::server::IdmServer;
+use kanidmd_lib::prelude::{duration_from_epoch_now, OperationError};
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::ecdsa::EcdsaSig;
+use openssl::error::ErrorStack;
+use openssl::hash::{hash, MessageDigest};
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::stack::Stack;
+use openssl::x509::extension::SubjectAlternativeName;
+use openssl::x509::{X509NameBuilder, X509ReqBuilder, X509};
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::{broadcast, Mutex, Notify};
+use tokio::time::{sleep, Duration};
+
+use crate::config::{AcmeChallengeType, AcmeConfig, TlsConfiguration};
+use crate::CoreAction;
+
+/// How often in seconds the certificate is checked for renewal.
+const ACME_CHECK_INTERVAL: u64 = 12 * 3600;
+
+/// How long in seconds to wait before trying again after an order failed.
+const ACME_RETRY_INTERVAL: u64 = 3600;
+
+/// How long in seconds to wait between checks of a pending authorization or order.
+const ACME_POLL_INTERVAL: u64 = 2;
+
+/// How many times a pending authorization or order is checked before it is abandoned.
+const ACME_POLL_ATTEMPTS: u32 = 60;
+
+const ACME_REQUEST_TIMEOUT: u64 = 30;
+
+const ACME_REPLAY_NONCE_HEADER: &str = "replay-nonce";
+const ACME_BAD_NONCE: &str = "urn:ietf:params:acme:error:badNonce";
+
+/// The key authorizations of the pending http-01 challenges, by their token.
+type AcmeChallenges = Arc<Mutex<BTreeMap<String, String>>>;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Deserialize)]
+struct Status {
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct Order {
+    status: String,
+    #[serde(default)]
+    authorizations: Vec<String>,
+    finalize: String,
+    certificate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Identifier {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+    #[serde(rename = "type")]
+    challenge_type: String,
+    url: String,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct Authorization {
+    status: String,
+    identifier: Identifier,
+    #[serde(default)]
+    challenges: Vec<Challenge>,
+}
+
+#[derive(Deserialize, Default)]
+struct Problem {
+    #[serde(rename = "type", default)]
+    problem_type: String,
+    #[serde(default)]
+    detail: String,
+}
+
+struct AcmeResponse {
+    location: Option<String>,
+    body: Vec<u8>,
+}
+
+fn b64url(data: &[u8]) -> String {
+    openssl::base64::encode_block(data)
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
+
+fn crypto_error(err: ErrorStack) -> OperationError {
+    error!(?err, "ACME cryptographic operation failed");
+    OperationError::CryptographyError
+}
+
+fn request_error(err: reqwest::Error) -> OperationError {
+    error!(?err, "Unable to contact the ACME certificate authority");
+    OperationError::KG007AcmeOrderFailed
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, OperationError> {
+    serde_json::from_slice(body).map_err(|err| {
+        error!(?err, "Invalid response from the ACME certificate authority");
+        OperationError::SerdeJsonError
+    })
+}
+
+fn generate_key() -> Result<PKey<Private>, OperationError> {
+    EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)
+        .and_then(|group| EcKey::generate(&group))
+        .and_then(PKey::from_ec_key)
+        .map_err(crypto_error)
+}
+
+/// Build a certificate signing request for these names.
+fn build_csr(key: &PKey<Private>, names: &[String]) -> Result<Vec<u8>, ErrorStack> {
+    let mut req_builder = X509ReqBuilder::new()?;
+    req_builder.set_pubkey(key)?;
+
+    let mut x509_name = X509NameBuilder::new()?;
+    // The common name is limited to 64 characters, the names are always in the SAN.
+    if let Some(name) = names.first().filter(|name| name.len() <= 64) {
+        x509_name.append_entry_by_text("CN", name)?;
+    }
+    req_builder.set_subject_name(&x509_name.build())?;
+
+    let mut san = SubjectAlternativeName::new();
+    for name in names {
+        san.dns(name);
+    }
+    let san = san.build(&req_builder.x509v3_context(None))?;
+    let mut extensions = Stack::new()?;
+    extensions.push(san)?;
+    req_builder.add_extensions(&extensions)?;
+
+    req_builder.sign(key, MessageDigest::sha256())?;
+    req_builder.build().to_der()
+}
+
+/// Write a file by replacing it, so that a reload never observes a partially written file.
+fn replace_file(path: &FsPath, contents: &[u8]) -> Result<(), OperationError> {
+    let mut tmp_path = path.as_os_str().to_os_string();
+    tmp_path.push(".acme");
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // The key must only be readable by the server.
+    #[cfg(not(target_family = "windows"))]
+    options.mode(0o600);
+
+    options
+        .open(&tmp_path)
+        .and_then(|mut file| file.write_all(contents))
+        .and_then(|_| fs::rename(&tmp_path, path))
+        .map_err(|err| {
+            error!(?err, path = %path.display(), "Unable to write TLS material");
+            OperationError::FsError
+        })
+}
+
+/// The state of a conversation with the certificate authority. The account key signs each
+/// request, and each response provides the nonce for the next request.
+struct AcmeSession<'a> {
+    client: &'a reqwest::Client,
+    directory: Directory,
+    key: PKey<Private>,
+    kid: Option<String>,
+    nonce: Option<String>,
+}
+
+impl<'a> AcmeSession<'a> {
+    async fn new(
+        client: &'a reqwest::Client,
+        directory_url: &str,
+        key: PKey<Private>,
+        kid: Option<String>,
+    ) -> Result<Self, OperationError> {
+        let body = client
+            .get(directory_url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(request_error)?
+            .bytes()
+            .await
+            .map_err(request_error)?;
+
+        let directory = parse(&body)?;
+
+        Ok(AcmeSession {
+            client,
+            directory,
+            key,
+            kid,
+            nonce: None,
+        })
+    }
+
+    /// The coordinates of the public account key as they are encoded in a JWK.
+    fn public_coordinates(&self) -> Result<(String, String), ErrorStack> {
+        let ec_key = self.key.ec_key()?;
+        let mut ctx = BigNumContext::new()?;
+        let mut x = BigNum::new()?;
+        let mut y = BigNum::new()?;
+        ec_key
+            .public_key()
+            .affine_coordinates_gfp(ec_key.group(), &mut x, &mut y, &mut ctx)?;
+        Ok((b64url(&x.to_vec_padded(32)?), b64url(&y.to_vec_padded(32)?)))
+    }
+
+    /// The key authorization of a challenge, which proves that this account responded to it.
+    fn key_authorization(&self, token: &str) -> Result<String, OperationError> {
+        let (x, y) = self.public_coordinates().map_err(crypto_error)?;
+        // The thumbprint is over the required members of the JWK in lexicographic order, with
+        // no whitespace (RFC 7638).
+        let jwk = format!(r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#, x, y);
+        let thumbprint = hash(MessageDigest::sha256(), jwk.as_bytes()).map_err(crypto_error)?;
+        Ok(format!("{}.{}", token, b64url(&thumbprint)))
+    }
+
+    fn sign(
+        &self,
+        url: &str,
+        nonce: String,
+        payload: Option<&serde_json::Value>,
+    ) -> Result<Vec<u8>, OperationError> {
+        let mut protected = json!({
+            "alg": "ES256",
+            "nonce": nonce,
+            "url": url,
+        });
+        // Until the account is registered it is identified by its key.
+        match &self.kid {
+            Some(kid) => protected["kid"] = json!(kid),
+            None => {
+                let (x, y) = self.public_coordinates().map_err(crypto_error)?;
+                protected["jwk"] = json!({ "crv": "P-256", "kty": "EC", "x": x, "y": y });
+            }
+        }
+
+        let protected = b64url(protected.to_string().as_bytes());
+        // POST-as-GET requests have an empty payload.
+        let payload = payload
+            .map(|payload| b64url(payload.to_string().as_bytes()))
+            .unwrap_or_default();
+
+        let signature = hash(
+            MessageDigest::sha256(),
+            format!("{}.{}", protected, payload).as_bytes(),
+        )
+        .and_then(|digest| {
+            let ec_key = self.key.ec_key()?;
+            EcdsaSig::sign(&digest, &ec_key)
+        })
+        .and_then(|sig| {
+            let mut signature = sig.r().to_vec_padded(32)?;
+            signature.extend(sig.s().to_vec_padded(32)?);
+            Ok(signature)
+        })
+        .map_err(crypto_error)?;
+
+        serde_json::to_vec(&json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": b64url(&signature),
+        }))
+        .map_err(|err| {
+            error!(?err, "Unable to serialise ACME request");
+            OperationError::SerdeJsonError
+        })
+    }
+
+    async fn new_nonce(&self) -> Result<String, OperationError> {
+        let response = self
+            .client
+            .head(&self.directory.new_nonce)
+            .send()
+            .await
+            .map_err(request_error)?;
+
+        response
+            .headers()
+            .get(ACME_REPLAY_NONCE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                error!("ACME certificate authority did not provide a nonce");
+                OperationError::KG007AcmeOrderFailed
+            })
+    }
+
+    /// Send a signed request. If the payload is `None` this is a POST-as-GET request.
+    async fn post(
+        &mut self,
+        url: &str,
+        payload: Option<serde_json::Value>,
+    ) -> Result<AcmeResponse, OperationError> {
+        let mut retried = false;
+
+        loop {
+            let nonce = match self.nonce.take() {
+                Some(nonce) => nonce,
+                None => self.new_nonce().await?,
+            };
+            let body = self.sign(url, nonce, payload.as_ref())?;
+
+            let response = self
+                .client
+                .post(url)
+                .header(CONTENT_TYPE, "application/jose+json")
+                .body(body)
+                .send()
+                .await
+                .map_err(request_error)?;
+
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+            };
+            self.nonce = header(ACME_REPLAY_NONCE_HEADER);
+            let location = header(LOCATION.as_str());
+            let status = response.status();
+
+            let body = response.bytes().await.map_err(request_error)?.to_vec();
+
+            if status.is_success() {
+                return Ok(AcmeResponse { location, body });
+            }
+
+            let problem: Problem = serde_json::from_slice(&body).unwrap_or_default();
+            // A nonce may expire before it is used, in which case a fresh nonce is provided.
+            if problem.problem_type == ACME_BAD_NONCE && !retried {
+                retried = true;
+                continue;
+            }
+
+            error!(
+                %url,
+                %status,
+                problem = %problem.problem_type,
+                detail = %problem.detail,
+                "ACME certificate authority rejected the request"
+            );
+            return Err(OperationError::KG007AcmeOrderFailed);
+        }
+    }
+
+    /// Check the status of an authorization or order until it is no longer in one of the
+    /// `pending` states, returning the final object.
+    async fn poll<T: DeserializeOwned>(
+        &mut self,
+        url: &str,
+        pending: &[&str],
+    ) -> Result<T, OperationError> {
+        for _ in 0..ACME_POLL_ATTEMPTS {
+            let response = self.post(url, None).await?;
+            let status: Status = parse(&response.body)?;
+            if !pending.contains(&status.status.as_str()) {
+                return parse(&response.body);
+            }
+            sleep(Duration::from_secs(ACME_POLL_INTERVAL)).await;
+        }
+
+        error!(%url, "Timed out waiting for the ACME certificate authority");
+        Err(OperationError::KG007AcmeOrderFailed)
+    }
+}
+
+async fn challenge_get(
+    State(challenges): State<AcmeChallenges>,
+    Path(token): Path<String>,
+) -> Result<String, StatusCode> {
+    challenges
+        .lock()
+        .await
+        .get(&token)
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Serve the key authorizations of the pending http-01 challenges.
+async fn challenge_server_loop(
+    addr: SocketAddr,
+    challenges: AcmeChallenges,
+    mut rx: broadcast::Receiver<CoreAction>,
+) {
+    let app = Router::new()
+        .route("/.well-known/acme-challenge/:token", get(challenge_get))
+        .with_state(challenges)
+        .into_make_service();
+
+    let listener = axum_server::bind(addr).serve(app);
+
+    pin_mut!(listener);
+
+    loop {
+        tokio::select! {
+            Ok(action) = rx.recv() => {
+                match action {
+                    CoreAction::Shutdown =>
+                        break,
+                }
+            }
+            result = &mut listener => {
+                if let Err(err) = result {
+                    error!(?err, %addr, "ACME challenge listener failed");
+                }
+                break;
+            }
+        }
+    }
+}
+
+pub(crate) struct AcmeActor {
+    config: AcmeConfig,
+    names: Vec<String>,
+    tls_config: TlsConfiguration,
+    idms: Arc<IdmServer>,
+    client: reqwest::Client,
+    challenges: AcmeChallenges,
+    tls_acceptor_reload_notify: Arc<Notify>,
+}
+
+impl AcmeActor {
+    pub fn start(
+        config: &AcmeConfig,
+        tls_config: &TlsConfiguration,
+        origin: &str,
+        idms: Arc<IdmServer>,
+        tls_acceptor_reload_notify: Arc<Notify>,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> Result<tokio::task::JoinHandle<()>, ()> {
+        if !config.terms_of_service_agreed {
+            error!(directory_url = %config.directory_url, "The terms of service of the ACME certificate authority must be agreed to with 'terms_of_service_agreed = true'");
+            return Err(());
+        }
+
+        if config.challenge == AcmeChallengeType::Dns01 && config.dns_hook.is_none() {
+            error!("The dns-01 ACME challenge requires a 'dns_hook'");
+            return Err(());
+        }
+
+        let names = if config.names.is_empty() {
+            url::Url::parse(origin)
+                .ok()
+                .and_then(|origin| origin.host_str().map(str::to_string))
+                .into_iter()
+                .collect()
+        } else {
+            config.names.clone()
+        };
+
+        if names.is_empty() {
+            error!("Unable to determine the names to request an ACME certificate for");
+            return Err(());
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(ACME_REQUEST_TIMEOUT))
+            .build()
+            .map_err(|err| {
+                error!(?err, "Unable to build ACME http client");
+            })?;
+
+        let challenges = AcmeChallenges::default();
+
+        let maybe_challenge_handle = match config.challenge {
+            AcmeChallengeType::Http01 => {
+                let addr = SocketAddr::from_str(&config.http_address).map_err(|err| {
+                    error!(?err, http_address = %config.http_address, "Invalid ACME http_address");
+                })?;
+                info!("Starting the ACME challenge listener on {} ...", addr);
+                Some(tokio::spawn(challenge_server_loop(
+                    addr,
+                    challenges.clone(),
+                    rx.resubscribe(),
+                )))
+            }
+            AcmeChallengeType::Dns01 => None,
+        };
+
+        let actor = AcmeActor {
+            config: config.clone(),
+            names,
+            tls_config: tls_config.clone(),
+            idms,
+            client,
+            challenges,
+            tls_acceptor_reload_notify,
+        };
+
+        Ok(tokio::spawn(async move {
+            loop {
+                let delay = tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                        }
+                    }
+                    result = actor.renew() => {
+                        match result {
+                            Ok(()) => ACME_CHECK_INTERVAL,
+                            Err(err) => {
+                                error!(?err, "Unable to renew the TLS certificate with ACME");
+                                ACME_RETRY_INTERVAL
+                            }
+                        }
+                    }
+                };
+
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                        }
+                    }
+                    _ = sleep(Duration::from_secs(delay)) => {}
+                }
+            }
+
+            if let Some(challenge_handle) = maybe_challenge_handle {
+                let _ = challenge_handle.await;
+            }
+
+            info!("Stopped {}", super::TaskName::AcmeActor);
+        }))
+    }
+
+    /// Order a certificate if the stored certificate is missing, expiring or is not for the
+    /// configured names, then install it if it isn't already.
+    async fn renew(&self) -> Result<(), OperationError> {
+        let stored = {
+            let mut idms_prox_write = self.idms.proxy_write(duration_from_epoch_now()).await?;
+            idms_prox_write.qs_write.acme_certificate()?
+        };
+
+        let (private, chain) = match stored {
+            Some((private, chain)) if self.is_current(&chain) => (private, chain),
+            _ => {
+                info!(names = ?self.names, "Ordering a TLS certificate with ACME");
+                let (private, chain) = self.order().await?;
+
+                let mut idms_prox_write = self.idms.proxy_write(duration_from_epoch_now()).await?;
+                idms_prox_write
+                    .qs_write
+                    .set_acme_certificate(private.clone(), &chain)?;
+                idms_prox_write.commit()?;
+
+                (private, chain)
+            }
+        };
+
+        self.install(&private, &chain)
+    }
+
+    fn is_current(&self, chain: &[X509]) -> bool {
+        let Some(leaf) = chain.first() else {
+            return false;
+        };
+
+        match expires_within_days(leaf, self.config.renew_before_days) {
+            Ok(false) => {}
+            Ok(true) => {
+                info!(not_after = %leaf.not_after(), "TLS certificate is due for renewal");
+                return false;
+            }
+            Err(err) => {
+                error!(?err, "Unable to check the expiry of the TLS certificate");
+                return false;
+            }
+        }
+
+        let leaf_names: BTreeSet<String> = leaf
+            .subject_alt_names()
+            .map(|sans| {
+                sans.iter()
+                    .filter_map(|san| san.dnsname().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let names: BTreeSet<String> = self.names.iter().cloned().collect();
+
+        if leaf_names != names {
+            info!(?leaf_names, ?names, "TLS certificate names have changed");
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Write the certificate to the TLS files and reload the acceptors, unless these files
+    /// already contain this certificate.
+    fn install(&self, private: &PKey<Private>, chain: &[X509]) -> Result<(), OperationError> {
+        let leaf = chain.first().ok_or_else(|| {
+            error!("ACME certificate chain is empty");
+            OperationError::InvalidState
+        })?;
+        let leaf_der = leaf.to_der().map_err(crypto_error)?;
+
+        let installed_der = fs::read(&self.tls_config.chain)
+            .ok()
+            .and_then(|pem| X509::from_pem(&pem).ok())
+            .and_then(|installed| installed.to_der().ok());
+
+        if installed_der.as_deref() == Some(leaf_der.as_slice()) {
+            debug!("TLS certificate is already installed");
+            return Ok(());
+        }
+
+        let key_pem = private.private_key_to_pem_pkcs8().map_err(crypto_error)?;
+        let mut chain_pem = Vec::new();
+        for x509 in chain {
+            chain_pem.extend(x509.to_pem().map_err(crypto_error)?);
+        }
+
+        replace_file(&self.tls_config.key, &key_pem)?;
+        replace_file(&self.tls_config.chain, &chain_pem)?;
+
+        info!(not_after = %leaf.not_after(), "Installed a new TLS certificate from ACME");
+        self.tls_acceptor_reload_notify.notify_one();
+        Ok(())
+    }
+
+    async fn account(&self) -> Result<AcmeSession<'_>, OperationError> {
+        let account = {
+            let mut idms_prox_write = self.idms.proxy_write(duration_from_epoch_now()).await?;
+            idms_prox_write.qs_write.acme_account()?
+        };
+
+        let (key, kid) = match account {
+            Some((key, kid)) => (key, kid),
+            None => (generate_key()?, None),
+        };
+
+        let mut session =
+            AcmeSession::new(&self.client, &self.config.directory_url, key, kid).await?;
+
+        if session.kid.is_none() {
+            let contact: Vec<_> = self
+                .config
+                .contact
+                .iter()
+                .map(|contact| {
+                    if contact.starts_with("mailto:") {
+                        contact.clone()
+                    } else {
+                        format!("mailto:{}", contact)
+                    }
+                })
+                .collect();
+
+            let new_account = session.directory.new_account.clone();
+            let response = session
+                .post(
+                    &new_account,
+                    Some(json!({
+                        "termsOfServiceAgreed": true,
+                        "contact": contact,
+                    })),
+                )
+                .await?;
+
+            let kid = response.location.ok_or_else(|| {
+                error!("ACME certificate authority did not provide the account url");
+                OperationError::KG007AcmeOrderFailed
+            })?;
+            info!(account = %kid, "Registered ACME account");
+
+            let mut idms_prox_write = self.idms.proxy_write(duration_from_epoch_now()).await?;
+            idms_prox_write
+                .qs_write
+                .set_acme_account(session.key.clone(), Some(kid.clone()))?;
+            idms_prox_write.commit()?;
+
+            session.kid = Some(kid);
+        }
+
+        Ok(session)
+    }
+
+    async fn order(&self) -> Result<(PKey<Private>, Vec<X509>), OperationError> {
+        let mut session = self.account().await?;
+
+        let identifiers: Vec<_> = self
+            .names
+            .iter()
+            .map(|name| json!({ "type": "dns", "value": name }))
+            .collect();
+
+        let new_order = session.directory.new_order.clone();
+        let response = session
+            .post(&new_order, Some(json!({ "identifiers": identifiers })))
+            .await?;
+
+        let order_url = response.location.ok_or_else(|| {
+            error!("ACME certificate authority did not provide the order url");
+            OperationError::KG007AcmeOrderFailed
+        })?;
+        let order: Order = parse(&response.body)?;
+
+        for authorization_url in &order.authorizations {
+            self.authorize(&mut session, authorization_url).await?;
+        }
+
+        let order: Order = session.poll(&order_url, &["pending"]).await?;
+        if order.status != "ready" && order.status != "valid" {
+            error!(status = %order.status, "ACME order was not authorized");
+            return Err(OperationError::KG007AcmeOrderFailed);
+        }
+
+        let private = generate_key()?;
+
+        if order.status == "ready" {
+            let csr = build_csr(&private, &self.names).map_err(crypto_error)?;
+            session
+                .post(&order.finalize, Some(json!({ "csr": b64url(&csr) })))
+                .await?;
+        }
+
+        let order: Order = session.poll(&order_url, &["ready", "processing"]).await?;
+        let certificate_url = match (order.status.as_str(), order.certificate) {
+            ("valid", Some(certificate_url)) => certificate_url,
+            (status, _) => {
+                error!(%status, "ACME order was not issued");
+                return Err(OperationError::KG007AcmeOrderFailed);
+            }
+        };
+
+        let response = session.post(&certificate_url, None).await?;
+        let chain = X509::stack_from_pem(&response.body).map_err(crypto_error)?;
+
+        let public_matches = chain
+            .first()
+            .and_then(|leaf| leaf.public_key().ok())
+            .is_some_and(|public| public.public_eq(&private));
+
+        if !public_matches {
+            error!("ACME certificate does not match the requested key");
+            return Err(OperationError::KG007AcmeOrderFailed);
+        }
+
+        Ok((private, chain))
+    }
+
+    async fn authorize(
+        &self,
+        session: &mut AcmeSession<'_>,
+        authorization_url: &str,
+    ) -> Result<(), OperationError> {
+        let response = session.post(authorization_url, None).await?;
+        let authorization: Authorization = parse(&response.body)?;
+
+        if authorization.status == "valid" {
+            return Ok(());
+        }
+
+        let domain = authorization.identifier.value;
+        let challenge_type = self.config.challenge.to_string();
+        let challenge = authorization
+            .challenges
+            .into_iter()
+            .find(|challenge| challenge.challenge_type == challenge_type)
+            .ok_or_else(|| {
+                error!(%domain, %challenge_type, "ACME certificate authority does not offer this challenge");
+                OperationError::KG007AcmeOrderFailed
+            })?;
+
+        let key_authorization = session.key_authorization(&challenge.token)?;
+
+        match self.config.challenge {
+            AcmeChallengeType::Http01 => {
+                self.challenges
+                    .lock()
+                    .await
+                    .insert(challenge.token.clone(), key_authorization.clone());
+            }
+            AcmeChallengeType::Dns01 => {
+                self.dns_hook("present", &domain, &key_authorization)
+                    .await?;
+            }
+        }
+
+        let result = async {
+            // An empty object tells the certificate authority that the challenge is ready.
+            session.post(&challenge.url, Some(json!({}))).await?;
+
+            let authorization: Authorization =
+                session.poll(authorization_url, &["pending"]).await?;
+
+            if authorization.status == "valid" {
+                info!(%domain, "ACME challenge succeeded");
+                Ok(())
+            } else {
+                error!(%domain, status = %authorization.status, "ACME challenge failed");
+                Err(OperationError::KG007AcmeOrderFailed)
+            }
+        }
+        .await;
+
+        match self.config.challenge {
+            AcmeChallengeType::Http01 => {
+                self.challenges.lock().await.remove(&challenge.token);
+            }
+            AcmeChallengeType::Dns01 => {
+                if let Err(err) = self.dns_hook("cleanup", &domain, &key_authorization).await {
+                    warn!(?err, %domain, "Unable to clean up the ACME dns challenge");
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Run the dns hook to present or clean up the TXT record of a dns-01 challenge.
+    async fn dns_hook(
+        &self,
+        action: &'static str,
+        domain: &str,
+        key_authorization: &str,
+    ) -> Result<(), OperationError> {
+        let Some(dns_hook) = self.config.dns_hook.clone() else {
+            error!("The dns-01 ACME challenge requires a 'dns_hook'");
+            return Err(OperationError::KG007AcmeOrderFailed);
+        };
+
+        let record = format!("_acme-challenge.{}", domain.trim_start_matches("*."));
+        let value = hash(MessageDigest::sha256(), key_authorization.as_bytes())
+            .map(|digest| b64url(&digest))
+            .map_err(crypto_error)?;
+
+        debug!(%action, %record, %value, "Running ACME dns hook");
+
+        let status = tokio::task::spawn_blocking(move || {
+            std::process::Command::new(&dns_hook)
+                .arg(action)
+                .arg(&record)
+                .arg(&value)
+                .status()
+        })
+        .await
+        .map_err(|err| {
+            error!(?err, "ACME dns hook task failed");
+            OperationError::KG002TaskCommFailure
+        })?
+        .map_err(|err| {
+            error!(?err, "Unable to run the ACME dns hook");
+            OperationError::KG007AcmeOrderFailed
+        })?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            error!(%action, %status, "ACME dns hook failed");
+            Err(OperationError::KG007AcmeOrderFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{b64url, build_csr, generate_key, AcmeSession};
+    use openssl::x509::X509Req;
+
+    #[test]
+    fn test_acme_b64url() {
+        assert_eq!(b64url(b""), "");
+        assert_eq!(b64url(&[0xfb, 0xff]), "-_8");
+        assert_eq!(b64url(b"{}"), "e30");
+    }
+
+    #[test]
+    fn test_acme_csr() {
+        let key = generate_key().expect("Unable to generate key");
+        let names = vec!["idm.example.com".to_string(), "example.com".to_string()];
+        let csr = build_csr(&key, &names).expect("Unable to build csr");
+
+        let req = X509Req::from_der(&csr).expect("Invalid csr");
+        assert!(req.verify(&key).expect("Unable to verify csr"));
+    }
+
+    #[test]
+    fn test_acme_key_authorization() {
+        let key = generate_key().expect("Unable to generate key");
+        let client = reqwest::Client::new();
+        let session = AcmeSession {
+            client: &client,
+            directory: super::Directory {
+                new_nonce: String::new(),
+                new_account: String::new(),
+                new_order: String::new(),
+            },
+            key,
+            kid: None,
+            nonce: None,
+        };
+
+        let key_authorization = session
+            .key_authorization("token")
+            .expect("Unable to build key authorization");
+        let (token, thumbprint) = key_authorization
+            .split_once('.')
+            .expect("Invalid key authorization");
+        assert_eq!(token, "token");
+        // A SHA-256 digest is 43 characters of unpadded base64.
+        assert_eq!(thumbprint.len(), 43);
+        assert!(!thumbprint.contains(['+', '/', '=']));
+    }
+}
@@ -3,6 +3,7 @@
 //! admin unixd socket.
 
 use crate::{QueryServerReadV1, QueryServerWriteV1};
+use tokio::sync::Mutex;
 use tracing::{Instrument, Level};
 
 use kanidmd_lib::prelude::*;
@@ -14,6 +15,10 @@ use kanidmd_lib::{
 
 use kanidm_proto::internal::{
     DomainInfo as ProtoDomainInfo, DomainUpgradeCheckReport as ProtoDomainUpgradeCheckReport,
+    KeyObjectReport as ProtoKeyObjectReport, QueryAnalysisReport as ProtoQueryAnalysisReport,
+    ReindexState as ProtoReindexState, ReindexStatus as ProtoReindexStatus,
+    ReplicationStatusReport as ProtoReplicationStatusReport, SchemaReport as ProtoSchemaReport,
+    SelfTestReport as ProtoSelfTestReport,
 };
 
 impl QueryServerReadV1 {
@@ -31,6 +36,81 @@ impl QueryServerReadV1 {
         idms_prox_read.qs_read.domain_info()
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_database_analyze(
+        &self,
+        eventid: Uuid,
+    ) -> Result<ProtoQueryAnalysisReport, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+
+        idms_prox_read.qs_read.query_analysis_report()
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_schema_show(
+        &self,
+        eventid: Uuid,
+    ) -> Result<ProtoSchemaReport, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+
+        idms_prox_read.qs_read.schema_report()
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_key_object_list(
+        &self,
+        eventid: Uuid,
+    ) -> Result<Vec<ProtoKeyObjectReport>, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+
+        idms_prox_read.qs_read.key_object_report()
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_replication_status(
+        &self,
+        eventid: Uuid,
+    ) -> Result<ProtoReplicationStatusReport, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+
+        idms_prox_read.qs_read.replication_status()
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_warm_entry_cache(
+        &self,
+        eventid: Uuid,
+    ) -> Result<usize, OperationError> {
+        let entries = {
+            let mut idms_prox_read = self.idms.proxy_read().await?;
+            idms_prox_read.qs_read.warm_entry_cache()?
+        };
+
+        self.idms.try_quiesce();
+
+        Ok(entries)
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -44,6 +124,27 @@ impl QueryServerReadV1 {
 
         idms_prox_read.qs_read.domain_upgrade_check()
     }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_self_test(
+        &self,
+        max_clock_skew: Duration,
+        eventid: Uuid,
+    ) -> Result<ProtoSelfTestReport, OperationError> {
+        let ct = duration_from_epoch_now();
+
+        let mut items = vec![self.idms.crypto_policy_self_test()];
+
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+
+        items.extend(idms_prox_read.qs_read.self_test(ct, max_clock_skew)?);
+
+        Ok(ProtoSelfTestReport { items })
+    }
 }
 
 impl QueryServerWriteV1 {
@@ -52,16 +153,25 @@ impl QueryServerWriteV1 {
         skip_all,
         fields(uuid = ?msg.eventid)
     )]
-    pub async fn handle_purgetombstoneevent(&self, msg: PurgeTombstoneEvent) {
+    pub async fn handle_purgetombstoneevent(&self, msg: PurgeTombstoneEvent, compact: bool) {
         let Ok(mut idms_prox_write) = self.idms.proxy_write(duration_from_epoch_now()).await else {
             warn!("Unable to start purge tombstone event, will retry later");
             return;
         };
 
+        // Reaping tombstones is what frees pages in the database, so compact in the same
+        // transaction to return them to the filesystem.
         let res = idms_prox_write
             .qs_write
             .purge_tombstones()
-            .and_then(|_changed| idms_prox_write.commit());
+            .and_then(|reaped| {
+                if compact && reaped > 0 {
+                    idms_prox_write.qs_write.compact().map(|_| ())
+                } else {
+                    Ok(())
+                }
+            })
+            .and_then(|()| idms_prox_write.commit());
 
         match res {
             Ok(()) => {
@@ -106,6 +216,67 @@ impl QueryServerWriteV1 {
         }
     }
 
+    #[instrument(level = "info", skip_all)]
+    pub async fn handle_account_lifecycle(&self) {
+        let ct = duration_from_epoch_now();
+        let Ok(mut idms_prox_write) = self.idms.proxy_write(ct).await else {
+            warn!("Unable to start account lifecycle event, will retry later");
+            return;
+        };
+
+        let res = idms_prox_write
+            .apply_account_lifecycle_policies(ct)
+            .and_then(|events| {
+                // don't need to commit a txn with no changes
+                if events.is_empty() {
+                    Ok(events)
+                } else {
+                    idms_prox_write.commit().map(|()| events)
+                }
+            });
+
+        match res {
+            Ok(events) => {
+                debug!(count = events.len(), "Account lifecycle success");
+                // The changes are only audited once they are committed.
+                for event in events {
+                    self.idms.submit_audit_event(event);
+                }
+            }
+            Err(err) => {
+                error!(?err, "Unable to apply account lifecycle policies");
+            }
+        }
+    }
+
+    pub async fn handle_purge_lapsed_group_members(&self) {
+        let ct = duration_from_epoch_now();
+        let Ok(mut idms_prox_write) = self.idms.proxy_write(ct).await else {
+            warn!("Unable to start purge lapsed group members event, will retry later");
+            return;
+        };
+
+        let res = idms_prox_write
+            .purge_lapsed_group_members(ct)
+            .and_then(|purged| {
+                // don't need to commit a txn with no changes
+                if purged > 0 {
+                    idms_prox_write.commit().map(|()| purged)
+                } else {
+                    Ok(purged)
+                }
+            });
+
+        match res {
+            Ok(purged) => {
+                debug!(?purged, "Purge lapsed group members success");
+            }
+            Err(err) => {
+                error!(?err, "Unable to purge lapsed group members");
+            }
+        }
+    }
+
     pub(crate) async fn handle_delayedaction(&self, da_batch: &mut Vec<DelayedAction>) {
         let eventid = Uuid::new_v4();
         let span = span!(Level::INFO, "process_delayed_action", uuid = ?eventid);
@@ -170,6 +341,24 @@ impl QueryServerWriteV1 {
         da_batch.clear();
     }
 
+    #[instrument(
+        level = "info",
+        skip(self, eventid),
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) fn handle_maintenance_mode(&self, enabled: Option<bool>, eventid: Uuid) -> bool {
+        if let Some(enabled) = enabled {
+            if enabled {
+                warn!("Maintenance mode enabled, changes from clients will be refused");
+            } else {
+                info!("Maintenance mode disabled");
+            }
+            self.idms.set_maintenance_mode(enabled);
+        }
+
+        self.idms.maintenance_mode()
+    }
+
     #[instrument(
         level = "info",
         skip(self, eventid),
@@ -201,6 +390,47 @@ impl QueryServerWriteV1 {
         idms_prox_write.commit().map(|()| DOMAIN_MAX_LEVEL)
     }
 
+    #[instrument(
+        level = "info",
+        skip(self, eventid),
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_key_object_rotate(
+        &self,
+        key_object: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        idms_prox_write
+            .qs_write
+            .key_object_rotate(&key_object, ct)?;
+
+        idms_prox_write.commit()
+    }
+
+    #[instrument(
+        level = "info",
+        skip(self, eventid),
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_key_object_revoke(
+        &self,
+        key_object: String,
+        key_id: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        idms_prox_write
+            .qs_write
+            .key_object_revoke(&key_object, &key_id)?;
+
+        idms_prox_write.commit()
+    }
+
     #[instrument(
         level = "info",
         skip(self, eventid),
@@ -219,4 +449,50 @@ impl QueryServerWriteV1 {
 
         idms_prox_write.commit()
     }
+
+    /// Rebuild the indexes of each attribute in its own write transaction, so that other
+    /// writes are able to proceed between them. Progress is recorded in `status`.
+    #[instrument(
+        level = "info",
+        skip(self, status, eventid),
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_online_reindex(
+        &self,
+        status: &Mutex<ProtoReindexStatus>,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let attrs = {
+            let ct = duration_from_epoch_now();
+            let idms_prox_write = self.idms.proxy_write(ct).await?;
+            // Nothing is written, so this transaction is dropped rather than committed.
+            idms_prox_write.qs_write.get_indexed_attributes()
+        };
+
+        status.lock().await.total = attrs.len();
+        info!(attributes = attrs.len(), "Online reindex: started");
+
+        for attr in attrs.iter() {
+            status.lock().await.attribute = Some(attr.to_string());
+
+            let ct = duration_from_epoch_now();
+            let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+            idms_prox_write.qs_write.reindex_attribute(attr)?;
+            idms_prox_write.commit()?;
+
+            status.lock().await.completed += 1;
+        }
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        idms_prox_write.qs_write.reindex_complete()?;
+        idms_prox_write.commit()?;
+
+        let mut status = status.lock().await;
+        status.state = ProtoReindexState::Complete;
+        status.attribute = None;
+        info!("Online reindex: complete");
+
+        Ok(())
+    }
 }
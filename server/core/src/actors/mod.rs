@@ -2,6 +2,8 @@
 //! components to conduct operations. These are separated based on protocol versions and
 //! if they are read or write transactions internally.
 
+use crate::audit::AuditStore;
+use crate::mail::Mailer;
 use kanidmd_lib::idm::ldap::LdapServer;
 use kanidmd_lib::idm::server::IdmServer;
 use std::sync::Arc;
@@ -9,16 +11,21 @@ use std::sync::Arc;
 pub struct QueryServerReadV1 {
     pub(crate) idms: Arc<IdmServer>,
     ldap: Arc<LdapServer>,
+    audit: Arc<AuditStore>,
 }
 
 impl QueryServerReadV1 {
-    pub fn new(idms: Arc<IdmServer>, ldap: Arc<LdapServer>) -> Self {
+    pub fn new(idms: Arc<IdmServer>, ldap: Arc<LdapServer>, audit: Arc<AuditStore>) -> Self {
         debug!("Starting query server read worker ...");
-        QueryServerReadV1 { idms, ldap }
+        QueryServerReadV1 { idms, ldap, audit }
     }
 
-    pub fn start_static(idms: Arc<IdmServer>, ldap: Arc<LdapServer>) -> &'static Self {
-        let x = Box::new(QueryServerReadV1::new(idms, ldap));
+    pub fn start_static(
+        idms: Arc<IdmServer>,
+        ldap: Arc<LdapServer>,
+        audit: Arc<AuditStore>,
+    ) -> &'static Self {
+        let x = Box::new(QueryServerReadV1::new(idms, ldap, audit));
 
         let x_ref = Box::leak(x);
         &(*x_ref)
@@ -27,16 +34,20 @@ impl QueryServerReadV1 {
 
 pub struct QueryServerWriteV1 {
     pub(crate) idms: Arc<IdmServer>,
+    mailer: Option<Arc<Mailer>>,
 }
 
 impl QueryServerWriteV1 {
-    pub fn new(idms: Arc<IdmServer>) -> Self {
+    pub fn new(idms: Arc<IdmServer>, mailer: Option<Arc<Mailer>>) -> Self {
         debug!("Starting a query server write worker ...");
-        QueryServerWriteV1 { idms }
+        QueryServerWriteV1 { idms, mailer }
     }
 
-    pub fn start_static(idms: Arc<IdmServer>) -> &'static QueryServerWriteV1 {
-        let x = Box::new(QueryServerWriteV1::new(idms));
+    pub fn start_static(
+        idms: Arc<IdmServer>,
+        mailer: Option<Arc<Mailer>>,
+    ) -> &'static QueryServerWriteV1 {
+        let x = Box::new(QueryServerWriteV1::new(idms, mailer));
 
         let x_ptr = Box::leak(x);
         &(*x_ptr)
@@ -5,48 +5,128 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use kanidm_proto::internal::{
-    ApiToken, AppLink, BackupCodesView, CURequest, CUSessionToken, CUStatus, CredentialStatus,
-    IdentifyUserRequest, IdentifyUserResponse, ImageValue, OperationError, RadiusAuthToken,
-    SearchRequest, SearchResponse, UserAuthToken,
+    AccessControlPreview, ApiToken, AppLink, ApplicationPassword, BackupCodesView, CUPasswordCheck,
+    CURequest, CUSessionToken, CUStatus, CredentialSoftLockStatus, CredentialStatus,
+    EffectiveAccountPolicy, EntryHistoryEvent, EntryHistoryResponse, EntryInspectResponse,
+    IdentifyUserRequest, IdentifyUserResponse, ImageValue, Oauth2Consent, OperationError,
+    RadiusAuthToken, SearchRequest, SearchResponse, SelfApiTokens, SelfSessions, SelfTestCheck,
+    SelfTestItem, SelfTestStatus, UiTheme, UserAuthToken, WebhookDelivery,
 };
 use kanidm_proto::oauth2::OidcWebfingerResponse;
 use kanidm_proto::v1::{
-    AuthIssueSession, AuthRequest, Entry as ProtoEntry, UatStatus, UnixGroupToken, UnixUserToken,
-    WhoamiResponse,
+    AuthCredential, AuthIssueSession, AuthRequest, AuthStep, BreakGlassChallenge,
+    BreakGlassCodeRequest, BreakGlassRequest, BreakGlassResponse, ChangesResponse,
+    Entry as ProtoEntry, GroupMembershipRequests, Oauth2SessionStatus, UatStatus, UnixGroupToken,
+    UnixUserToken, WhoamiResponse,
 };
 use kanidmd_lib::idm::identityverification::{
     IdentifyUserDisplayCodeEvent, IdentifyUserStartEvent, IdentifyUserSubmitCodeEvent,
 };
+use ldap3_proto::proto::LdapOp;
 use ldap3_proto::simple::*;
 use regex::Regex;
+use serde::Deserialize;
 use tracing::{error, info, instrument, trace};
 use uuid::Uuid;
+use webauthn_rs::prelude::{PublicKeyCredential, RequestChallengeResponse};
 
 use compact_jwt::{JweCompact, Jwk, JwsCompact};
 
 use kanidmd_lib::be::BackendTransaction;
 use kanidmd_lib::prelude::*;
+use kanidmd_lib::server::apply::entry_version;
 use kanidmd_lib::{
     event::{OnlineBackupEvent, SearchEvent, SearchResult, WhoamiResult},
     filter::{Filter, FilterInvalid},
     idm::account::ListUserAuthTokenEvent,
+    idm::audit::{AuditEvent, AuditRecord},
     idm::credupdatesession::CredentialUpdateSessionToken,
+    idm::dpop::DPoPProof,
     idm::event::{
-        AuthEvent, AuthResult, CredentialStatusEvent, RadiusAuthTokenEvent, ReadBackupCodeEvent,
-        UnixGroupTokenEvent, UnixUserAuthEvent, UnixUserTokenEvent,
+        AuthEvent, AuthResult, CredentialStatusEvent, RadiusAuthTokenEvent, ReadAccountPolicyEvent,
+        ReadBackupCodeEvent, UnixGroupTokenEvent, UnixUserAuthEvent, UnixUserTokenEvent,
     },
-    idm::ldap::{LdapBoundToken, LdapResponseState},
+    idm::ldap::{LdapBoundToken, LdapResponseState, LDAP_EXOP_PASSWORD_MODIFY},
     idm::oauth2::{
         AccessTokenIntrospectRequest, AccessTokenIntrospectResponse, AuthorisationRequest,
-        AuthoriseReject, AuthoriseResponse, JwkKeySet, Oauth2Error, Oauth2Rfc8414MetadataResponse,
-        OidcDiscoveryResponse, OidcToken,
+        AuthoriseReject, AuthoriseResponse, JwkKeySet, ListOauth2SessionEvent, Oauth2Error,
+        Oauth2Rfc8414MetadataResponse, OidcDiscoveryResponse, OidcToken,
     },
-    idm::server::{DomainInfoRead, IdmServerTransaction},
+    idm::oidcupstream::OidcUpstreamExchange,
+    idm::saml2::Saml2SsoResponse,
+    idm::server::{DomainInfoRead, IdmServerTransaction, Token},
     idm::serviceaccount::ListApiTokenEvent,
     idm::ClientAuthInfo,
 };
 
 use super::QueryServerReadV1;
+use crate::audit::AuditQuery;
+use crate::backup::S3BackupStore;
+
+/// How long to wait for an upstream identity provider to respond, in seconds.
+const OIDC_UPSTREAM_REQUEST_TIMEOUT: u64 = 10;
+
+#[derive(Deserialize)]
+struct OidcUpstreamTokenResponse {
+    id_token: String,
+}
+
+/// Exchange the code from an upstream identity provider for an id token, and fetch the keys
+/// that it is signed with.
+async fn oidc_upstream_exchange(
+    exchange: &OidcUpstreamExchange,
+    code: &str,
+) -> Result<(String, JwkKeySet), OperationError> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(OIDC_UPSTREAM_REQUEST_TIMEOUT))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|err| {
+            error!(?err, "Unable to build upstream http client");
+            OperationError::AU0009FederatedExchangeFailed
+        })?;
+
+    let token_response: OidcUpstreamTokenResponse = client
+        .post(exchange.token_endpoint.clone())
+        .basic_auth(&exchange.client_id, Some(&exchange.client_secret))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", exchange.redirect_uri.as_str()),
+            ("code_verifier", exchange.code_verifier.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| {
+            error!(?err, token_endpoint = %exchange.token_endpoint, "Upstream code exchange failed");
+            OperationError::AU0009FederatedExchangeFailed
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!(?err, "Upstream token response is invalid");
+            OperationError::AU0009FederatedExchangeFailed
+        })?;
+
+    let jwks: JwkKeySet = client
+        .get(exchange.jwks_uri.clone())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| {
+            error!(?err, jwks_uri = %exchange.jwks_uri, "Unable to fetch upstream keys");
+            OperationError::AU0009FederatedExchangeFailed
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!(?err, "Upstream keys are invalid");
+            OperationError::AU0009FederatedExchangeFailed
+        })?;
+
+    Ok((token_response.id_token, jwks))
+}
 
 // ===========================================================
 
@@ -142,6 +222,192 @@ impl QueryServerReadV1 {
         res
     }
 
+    #[instrument(
+        level = "info",
+        name = "auth_discoverable_begin",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_auth_discoverable_begin(
+        &self,
+        eventid: Uuid,
+    ) -> Result<(Uuid, RequestChallengeResponse), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idm_auth = self.idms.auth().await?;
+
+        idm_auth.expire_auth_sessions(ct).await;
+
+        idm_auth
+            .auth_discoverable_begin(ct)
+            .await
+            .and_then(|r| idm_auth.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        name = "auth_discoverable",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_auth_discoverable(
+        &self,
+        challenge_id: Uuid,
+        credential: Box<PublicKeyCredential>,
+        issue: AuthIssueSession,
+        eventid: Uuid,
+        client_auth_info: ClientAuthInfo,
+    ) -> Result<AuthResult, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idm_auth = self.idms.auth().await?;
+        security_info!(?challenge_id, "Begin discoverable auth event");
+
+        // As with the username flow, expired challenges are removed before they can be used.
+        idm_auth.expire_auth_sessions(ct).await;
+
+        let res = idm_auth
+            .auth_discoverable(challenge_id, credential, issue, ct, client_auth_info)
+            .await
+            .and_then(|r| idm_auth.commit().map(|_| r));
+
+        security_info!(?res, "Sending auth result");
+
+        res
+    }
+
+    #[instrument(
+        level = "info",
+        name = "break_glass_begin",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_break_glass_begin(
+        &self,
+        eventid: Uuid,
+    ) -> Result<BreakGlassChallenge, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idm_auth = self.idms.auth().await?;
+
+        idm_auth.expire_auth_sessions(ct).await;
+
+        let (id, challenge) = idm_auth.break_glass_begin(ct)?;
+        idm_auth.commit()?;
+
+        Ok(BreakGlassChallenge { id, challenge })
+    }
+
+    #[instrument(
+        level = "info",
+        name = "break_glass",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_break_glass(
+        &self,
+        request: BreakGlassRequest,
+        eventid: Uuid,
+        client_auth_info: ClientAuthInfo,
+    ) -> Result<BreakGlassResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idm_auth = self.idms.auth().await?;
+        security_info!(challenge_id = ?request.id, "Begin break glass auth event");
+
+        idm_auth.expire_auth_sessions(ct).await;
+
+        let token = idm_auth.break_glass_finish(
+            request.id,
+            &request.signature,
+            ct,
+            client_auth_info.source,
+        )?;
+        idm_auth.commit()?;
+
+        Ok(BreakGlassResponse {
+            token: token.to_string(),
+        })
+    }
+
+    #[instrument(
+        level = "info",
+        name = "break_glass_code",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_break_glass_code(
+        &self,
+        request: BreakGlassCodeRequest,
+        eventid: Uuid,
+        client_auth_info: ClientAuthInfo,
+    ) -> Result<BreakGlassResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idm_auth = self.idms.auth().await?;
+        security_info!(account = %request.account, "Begin break glass recovery code auth event");
+
+        idm_auth.expire_auth_sessions(ct).await;
+
+        let token = idm_auth.break_glass_code(
+            request.account.as_str(),
+            request.code.as_str(),
+            ct,
+            client_auth_info.source,
+        )?;
+        idm_auth.commit()?;
+
+        Ok(BreakGlassResponse {
+            token: token.to_string(),
+        })
+    }
+
+    #[instrument(
+        level = "info",
+        name = "auth_federated",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_auth_federated(
+        &self,
+        sessionid: Uuid,
+        state: String,
+        code: String,
+        eventid: Uuid,
+        client_auth_info: ClientAuthInfo,
+    ) -> Result<AuthResult, OperationError> {
+        let ct = duration_from_epoch_now();
+        security_info!(?sessionid, "Begin federated auth event");
+
+        let exchange = {
+            let mut idm_auth = self.idms.auth().await?;
+            idm_auth.expire_auth_sessions(ct).await;
+            let exchange = idm_auth.auth_federated_exchange(sessionid, &state).await?;
+            idm_auth.commit()?;
+            exchange
+        };
+
+        // The provider must not be contacted while a transaction is held.
+        let (id_token, jwks) = oidc_upstream_exchange(&exchange, &code).await?;
+
+        let ct = duration_from_epoch_now();
+        let mut idm_auth = self.idms.auth().await?;
+        idm_auth
+            .auth_federated_verify(sessionid, &id_token, &jwks, ct)
+            .await?;
+
+        let ae = AuthEvent::from_message(
+            Some(sessionid),
+            AuthRequest {
+                step: AuthStep::Cred(AuthCredential::Federated(state)),
+            },
+        )?;
+
+        let res = idm_auth
+            .auth(&ae, ct, client_auth_info)
+            .await
+            .and_then(|r| idm_auth.commit().map(|_| r));
+
+        security_info!(?res, "Sending auth result");
+
+        res
+    }
+
     #[instrument(
         level = "info",
         name = "reauth",
@@ -310,6 +576,56 @@ impl QueryServerReadV1 {
         Ok(())
     }
 
+    #[instrument(
+        level = "info",
+        name = "online_backup_s3",
+        skip_all,
+        fields(uuid = ?msg.eventid)
+    )]
+    pub(crate) async fn handle_online_backup_s3(
+        &self,
+        msg: OnlineBackupEvent,
+        store: &S3BackupStore,
+        versions: usize,
+    ) -> Result<(), OperationError> {
+        trace!(eventid = ?msg.eventid, "Begin online backup event");
+
+        let now = time::OffsetDateTime::now_utc();
+
+        #[allow(clippy::unwrap_used)]
+        let timestamp = now.format(&Rfc3339).unwrap();
+        let name = format!("backup-{}", timestamp);
+
+        // Scope to limit the read txn, the upload happens after it is released.
+        let backup = {
+            let mut idms_prox_read = self.idms.proxy_read().await?;
+            idms_prox_read
+                .qs_read
+                .get_be_txn()
+                .backup_data()
+                .map_err(|e| {
+                    error!("Online backup failed to create {}: {:?}", name, e);
+                    OperationError::InvalidState
+                })?
+        };
+
+        store.upload(&name, backup.as_bytes(), now).await?;
+        info!("Online backup uploaded {} successfully", name);
+
+        // pattern to find automatically generated backups
+        let re = Regex::new(r"^backup-\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d{1,9})?Z$").map_err(
+            |error| {
+                error!(
+                    "Failed to parse regexp for online backup objects: {:?}",
+                    error
+                );
+                OperationError::InvalidState
+            },
+        )?;
+
+        store.apply_retention(&re, versions).await
+    }
+
     #[instrument(
         level = "info",
         name = "whoami",
@@ -384,6 +700,126 @@ impl QueryServerReadV1 {
             })
     }
 
+    #[instrument(
+        level = "debug",
+        name = "bound_session_id",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    /// The id of the session in a token that was just issued, if the session is bound to a key
+    /// held by the browser. The browser includes this in its proofs once it knows it.
+    pub async fn handle_bound_session_id(
+        &self,
+        token: JwsCompact,
+        eventid: Uuid,
+    ) -> Result<Option<Uuid>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+
+        match idms_prox_read.validate_and_parse_token_to_token(&token, ct)? {
+            Token::UserAuthToken(uat) => Ok(uat.session_key.map(|_| uat.session_id)),
+            Token::ApiToken(..) => Ok(None),
+        }
+    }
+
+    #[instrument(
+        level = "debug",
+        name = "idempotency_scope",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    /// The session that a request is authenticated with. The outcomes of idempotent writes are
+    /// only kept for authenticated requests, and are scoped to this session.
+    pub async fn handle_idempotency_scope(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<Uuid, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read.validate_client_auth_info_to_ident(client_auth_info, ct)?;
+
+        match ident.get_uuid() {
+            Some(uuid) if uuid != UUID_ANONYMOUS => Ok(ident.get_session_id()),
+            _ => Err(OperationError::NotAuthenticated),
+        }
+    }
+
+    #[instrument(
+        level = "debug",
+        name = "auth_account_uuid",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    /// Resolve the account that an authentication names, so that attempts against the account
+    /// are counted together regardless of whether they use its name, spn or uuid.
+    pub async fn handle_auth_account_uuid(
+        &self,
+        username: String,
+        eventid: Uuid,
+    ) -> Result<Option<Uuid>, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+
+        match idms_prox_read.qs_read.name_to_uuid(username.as_str()) {
+            Ok(uuid) => Ok(Some(uuid)),
+            Err(OperationError::NoMatchingEntries) | Err(OperationError::InvalidValueState) => {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    #[instrument(
+        level = "debug",
+        name = "ui_theme",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    /// Get the web interface theme chosen by the authenticated person, if any.
+    pub async fn handle_ui_theme(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<Option<UiTheme>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read.validate_client_auth_info_to_ident(client_auth_info, ct)?;
+
+        Ok(ident
+            .get_user_entry()
+            .and_then(|entry| {
+                entry
+                    .get_ava_single_iutf8(Attribute::UiTheme)
+                    .map(str::to_string)
+            })
+            .and_then(|theme| UiTheme::from_str(&theme).ok()))
+    }
+
+    #[instrument(
+        level = "debug",
+        name = "idm_admin_check",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    /// Check if the authenticated account is a member of idm_admins, and so may use the
+    /// administration pages of the web interface.
+    pub async fn handle_idm_admin_check(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<bool, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read.validate_client_auth_info_to_ident(client_auth_info, ct)?;
+
+        Ok(ident
+            .get_user_entry()
+            .map(|entry| {
+                entry.attribute_equality(Attribute::MemberOf, &PartialValue::Refer(UUID_IDM_ADMINS))
+            })
+            .unwrap_or_default())
+    }
+
     #[instrument(level = "debug", skip_all)]
     /// pull an image so we can present it to the user
     pub async fn handle_oauth2_rs_image_get_image(
@@ -458,6 +894,54 @@ impl QueryServerReadV1 {
         }
     }
 
+    /// Search for a single entry, returning it with its version for use in If-Match.
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_internalsearch_versioned(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        filter: Filter<FilterInvalid>,
+        eventid: Uuid,
+    ) -> Result<Option<(ProtoEntry, String)>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+        let srch =
+            SearchEvent::from_internal_message(ident, &filter, None, &mut idms_prox_read.qs_read)
+                .map_err(|e| {
+                error!("Failed to begin internal api search: {:?}", e);
+                e
+            })?;
+
+        trace!(?srch, "Begin event");
+
+        let mut entries = idms_prox_read.qs_read.search_ext(&srch)?;
+        let Some(entry) = entries.pop() else {
+            return Ok(None);
+        };
+
+        let version = idms_prox_read
+            .qs_read
+            .internal_search_uuid(entry.get_uuid())
+            .ok()
+            .and_then(|entry| entry_version(entry.as_ref()))
+            .ok_or(OperationError::InvalidEntryState)?;
+
+        SearchResult::new(&mut idms_prox_read.qs_read, &[entry]).map(|sr| {
+            sr.into_proto_array()
+                .pop()
+                .map(|proto_entry| (proto_entry, version))
+        })
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -805,6 +1289,12 @@ impl QueryServerReadV1 {
 
         trace!(?srch, "Begin event");
 
+        // Expired keys are not distributed to clients.
+        let expired = idms_prox_read
+            .qs_read
+            .internal_search_uuid(target_uuid)?
+            .get_sshpubkeys_expired(ct);
+
         match idms_prox_read.qs_read.search_ext(&srch) {
             Ok(mut entries) => {
                 let r = entries
@@ -812,8 +1302,14 @@ impl QueryServerReadV1 {
                     // get the first entry
                     .and_then(|e| {
                         // From the entry, turn it into the value
-                        e.get_ava_iter_sshpubkeys(Attribute::SshPublicKey)
-                            .map(|i| i.collect())
+                        e.get_ava_set(Attribute::SshPublicKey)
+                            .and_then(|vs| vs.as_sshkey_map())
+                            .map(|keys| {
+                                keys.iter()
+                                    .filter(|(tag, _)| !expired.contains(*tag))
+                                    .map(|(_, pk)| pk.to_string())
+                                    .collect()
+                            })
                     })
                     .unwrap_or_else(|| {
                         // No matching entry? Return none.
@@ -867,7 +1363,17 @@ impl QueryServerReadV1 {
 
         trace!(?srch, "Begin event");
 
-        match idms_prox_read.qs_read.search_ext(&srch) {
+        // Expired keys are not distributed to clients.
+        if idms_prox_read
+            .qs_read
+            .internal_search_uuid(target_uuid)?
+            .get_sshpubkeys_expired(ct)
+            .contains(&tag)
+        {
+            return Ok(None);
+        }
+
+        match idms_prox_read.qs_read.search_ext(&srch) {
             Ok(mut entries) => {
                 let r = entries
                     .pop()
@@ -920,6 +1426,28 @@ impl QueryServerReadV1 {
         idms_prox_read.service_account_list_api_token(&lte)
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_list_self_api_tokens(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<SelfApiTokens, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        idms_prox_read.account_list_self_api_tokens(&ident)
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -951,6 +1479,68 @@ impl QueryServerReadV1 {
         idms_prox_read.account_list_user_auth_tokens(&lte)
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_oauth2_session_get(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<Vec<Oauth2SessionStatus>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+        let target = idms_prox_read
+            .qs_read
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
+
+        let lse = ListOauth2SessionEvent { ident, target };
+
+        idms_prox_read.oauth2_list_sessions(&lse)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_account_policy_read(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<EffectiveAccountPolicy, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+        let target = idms_prox_read
+            .qs_read
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
+
+        let rape = ReadAccountPolicyEvent { ident, target };
+
+        idms_prox_read.get_account_policy(&rape)
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1088,6 +1678,73 @@ impl QueryServerReadV1 {
         idms_prox_read.get_credentialstatus(&cse)
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmcredentialsoftlockstatus(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<CredentialSoftLockStatus, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idm_auth = self.idms.auth().await?;
+
+        let ident = idm_auth
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+        let target_uuid = idm_auth
+            .qs_read
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        idm_auth
+            .credential_softlock_status(&ident, target_uuid, ct)
+            .await
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmcredentialsoftlockclear(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idm_auth = self.idms.auth().await?;
+
+        let ident = idm_auth
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+        let target_uuid = idm_auth
+            .qs_read
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        idm_auth
+            .credential_softlock_clear(&ident, target_uuid)
+            .await
+            .and_then(|()| idm_auth.commit())
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1166,6 +1823,69 @@ impl QueryServerReadV1 {
             .map(|sta| sta.into())
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmcredentialsuggestpassphrase(
+        &self,
+        session_token: CUSessionToken,
+        eventid: Uuid,
+    ) -> Result<String, OperationError> {
+        let session_token = JweCompact::from_str(&session_token.token)
+            .map(|token_enc| CredentialUpdateSessionToken { token_enc })
+            .map_err(|err| {
+                error!(?err, "malformed token");
+                OperationError::InvalidRequestState
+            })?;
+
+        let ct = duration_from_epoch_now();
+        let idms_cred_update = self.idms.cred_update_transaction().await?;
+
+        idms_cred_update
+            .credential_primary_suggest_passphrase(&session_token, ct)
+            .map_err(|e| {
+                error!(
+                    err = ?e,
+                    "Failed to begin credential_primary_suggest_passphrase",
+                );
+                e
+            })
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmcredentialcheckpassword(
+        &self,
+        session_token: CUSessionToken,
+        pw: String,
+        eventid: Uuid,
+    ) -> Result<CUPasswordCheck, OperationError> {
+        let session_token = JweCompact::from_str(&session_token.token)
+            .map(|token_enc| CredentialUpdateSessionToken { token_enc })
+            .map_err(|err| {
+                error!(?err, "malformed token");
+                OperationError::InvalidRequestState
+            })?;
+
+        let ct = duration_from_epoch_now();
+        let idms_cred_update = self.idms.cred_update_transaction().await?;
+
+        idms_cred_update
+            .credential_primary_check_password(&session_token, ct, &pw)
+            .map_err(|e| {
+                error!(
+                    err = ?e,
+                    "Failed to begin credential_primary_check_password",
+                );
+                e
+            })
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1485,6 +2205,8 @@ impl QueryServerReadV1 {
         &self,
         client_id: String,
         token: JwsCompact,
+        dpop: Option<DPoPProof>,
+        client_cert: Option<ClientCertInfo>,
         eventid: Uuid,
     ) -> Result<OidcToken, Oauth2Error> {
         let ct = duration_from_epoch_now();
@@ -1493,7 +2215,13 @@ impl QueryServerReadV1 {
             .proxy_read()
             .await
             .map_err(Oauth2Error::ServerError)?;
-        idms_prox_read.oauth2_openid_userinfo(&client_id, token, ct)
+        idms_prox_read.oauth2_openid_userinfo(
+            &client_id,
+            token,
+            dpop.as_ref(),
+            client_cert.as_ref(),
+            ct,
+        )
     }
 
     #[instrument(
@@ -1553,6 +2281,154 @@ impl QueryServerReadV1 {
         idms_prox_read.oauth2_openid_publickey(&client_id)
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_audit_query(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        query: AuditQuery,
+        eventid: Uuid,
+    ) -> Result<Vec<AuditRecord>, OperationError> {
+        let ct = duration_from_epoch_now();
+        // Scope the read txn, we only need it to resolve the identity.
+        {
+            let mut idms_prox_read = self.idms.proxy_read().await?;
+            let ident = idms_prox_read
+                .validate_client_auth_info_to_ident(client_auth_info, ct)
+                .map_err(|e| {
+                    error!("Invalid identity: {:?}", e);
+                    e
+                })?;
+
+            // The audit store is outside of the database, so access controls can't
+            // be applied. Limit this to system administrators.
+            if !ident.is_memberof(UUID_SYSTEM_ADMINS) {
+                security_access!("Identity is not permitted to query audit events");
+                return Err(OperationError::AccessDenied);
+            }
+        }
+
+        let now = time::OffsetDateTime::UNIX_EPOCH + ct;
+        self.audit.query(&query, now)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_access_control_preview(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        sample_size: usize,
+        eventid: Uuid,
+    ) -> Result<AccessControlPreview, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        // The preview reveals the rights of other accounts, which the access controls
+        // themself can't express. Limit this to access control administrators.
+        if !ident.is_memberof(UUID_IDM_ACCESS_CONTROL_ADMINS) {
+            security_access!("Identity is not permitted to preview access controls");
+            return Err(OperationError::AccessDenied);
+        }
+
+        idms_prox_read.qs_read.access_control_preview(sample_size)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_webhook_secret_read(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        filter: Filter<FilterInvalid>,
+        eventid: Uuid,
+    ) -> Result<Option<String>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        let srch =
+            SearchEvent::from_internal_message(ident, &filter, None, &mut idms_prox_read.qs_read)
+                .inspect_err(|err| error!(?err, "Failed to begin webhook secret read"))?;
+
+        trace!(?srch, "Begin event");
+
+        // We have to use search_ext to guarantee acs was applied.
+        let mut entries = idms_prox_read.qs_read.search_ext(&srch)?;
+
+        Ok(entries.pop().and_then(|entry| {
+            entry
+                .get_ava_single(Attribute::WebhookSecret)
+                .and_then(|v| v.get_secret_str().map(str::to_string))
+        }))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_webhook_deliveries(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        filter: Filter<FilterInvalid>,
+        eventid: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, OperationError> {
+        let ct = duration_from_epoch_now();
+        // Scope the read txn, we only need it to resolve the identity and the webhook.
+        let webhook = {
+            let mut idms_prox_read = self.idms.proxy_read().await?;
+            let ident = idms_prox_read
+                .validate_client_auth_info_to_ident(client_auth_info, ct)
+                .map_err(|e| {
+                    error!("Invalid identity: {:?}", e);
+                    e
+                })?;
+
+            // Delivery status is held in the audit store, so access controls can't
+            // be applied. Limit this to system administrators.
+            if !ident.is_memberof(UUID_SYSTEM_ADMINS) {
+                security_access!("Identity is not permitted to read webhook deliveries");
+                return Err(OperationError::AccessDenied);
+            }
+
+            let srch = SearchEvent::from_internal_message(
+                ident,
+                &filter,
+                None,
+                &mut idms_prox_read.qs_read,
+            )?;
+
+            idms_prox_read
+                .qs_read
+                .search_ext(&srch)?
+                .pop()
+                .map(|entry| entry.get_uuid())
+                .ok_or(OperationError::NoMatchingEntries)?
+        };
+
+        let now = time::OffsetDateTime::UNIX_EPOCH + ct;
+        self.audit.webhook_deliveries(webhook, now)
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1576,6 +2452,223 @@ impl QueryServerReadV1 {
         idms_prox_read.list_applinks(&ident)
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_list_self_application_passwords(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<Vec<ApplicationPassword>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        idms_prox_read.list_self_application_passwords(&ident)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_list_self_oauth2_consents(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<Vec<Oauth2Consent>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        idms_prox_read.list_self_oauth2_consents(&ident)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_list_self_sessions(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<SelfSessions, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        idms_prox_read.list_self_sessions(&ident, ct)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_list_group_membership_requests(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<GroupMembershipRequests, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        idms_prox_read.list_group_membership_requests(&ident)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_changes(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        cursor: Option<String>,
+        eventid: Uuid,
+    ) -> Result<ChangesResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+
+        idms_prox_read.changes_since(&ident, cursor.as_deref())
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_entry_inspect(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<EntryInspectResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+        let target = idms_prox_read
+            .qs_read
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
+
+        idms_prox_read.inspect_entry(&ident, target)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_entry_history(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<EntryHistoryResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        // Scope the read txn, the audit store is queried once the entry is inspected. Access
+        // to both is limited to system administrators by inspect_entry.
+        let entry = {
+            let mut idms_prox_read = self.idms.proxy_read().await?;
+            let ident = idms_prox_read
+                .validate_client_auth_info_to_ident(client_auth_info, ct)
+                .map_err(|e| {
+                    error!("Invalid identity: {:?}", e);
+                    e
+                })?;
+            let target = idms_prox_read
+                .qs_read
+                .name_to_uuid(uuid_or_name.as_str())
+                .inspect_err(|err| {
+                    error!(?err, "Error resolving id to target");
+                })?;
+
+            idms_prox_read.inspect_entry(&ident, target)?
+        };
+
+        let query = AuditQuery {
+            target: Some(entry.uuid),
+            ..Default::default()
+        };
+        let now = time::OffsetDateTime::UNIX_EPOCH + ct;
+
+        let events = self
+            .audit
+            .query(&query, now)?
+            .into_iter()
+            .filter_map(|record| {
+                // Only the events that changed the entry are part of its history.
+                let (actor, attrs) = match &record.event {
+                    AuditEvent::EntriesCreated { actor, .. }
+                    | AuditEvent::EntriesDeleted { actor, .. } => (Some(*actor), Vec::new()),
+                    AuditEvent::EntriesModified { actor, attrs, .. } => {
+                        (Some(*actor), attrs.iter().map(|a| a.to_string()).collect())
+                    }
+                    AuditEvent::CredentialUpdated { .. }
+                    | AuditEvent::AccountLifecycleExpirySet { .. }
+                    | AuditEvent::AccountLifecycleArchived { .. } => (None, Vec::new()),
+                    _ => return None,
+                };
+
+                let time = record
+                    .event
+                    .time()
+                    .format(&time::format_description::well_known::Rfc3339)
+                    .map_err(|err| {
+                        error!(?err, "Unable to format audit event time");
+                        OperationError::InvalidState
+                    });
+
+                Some(time.map(|time| EntryHistoryEvent {
+                    time,
+                    event: record.event.name().to_string(),
+                    actor,
+                    attrs,
+                }))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(EntryHistoryResponse { entry, events })
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1626,6 +2719,17 @@ impl QueryServerReadV1 {
         uat: Option<LdapBoundToken>,
         ip_addr: IpAddr,
     ) -> Option<LdapResponseState> {
+        // Password modify is not one of the simple server ops, so it's handled here.
+        if let LdapOp::ExtendedRequest(ler) = &protomsg.op {
+            if ler.name == LDAP_EXOP_PASSWORD_MODIFY {
+                return Some(
+                    self.ldap
+                        .do_password_modify(&self.idms, protomsg.msgid, ler, uat, ip_addr)
+                        .await,
+                );
+            }
+        }
+
         let res = match ServerOps::try_from(protomsg) {
             Ok(server_op) => self
                 .ldap
@@ -1646,6 +2750,65 @@ impl QueryServerReadV1 {
         Some(res)
     }
 
+    #[instrument(
+        level = "debug",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    /// Check that the database and key providers are available to serve requests.
+    pub async fn handle_readiness_check(&self, eventid: Uuid) -> Vec<SelfTestItem> {
+        match self.idms.proxy_read().await {
+            Ok(mut idms_prox_read) => idms_prox_read.qs_read.readiness_check(),
+            Err(err) => {
+                error!(?err, "Unable to begin readiness check");
+                vec![SelfTestItem {
+                    check: SelfTestCheck::Database,
+                    status: SelfTestStatus::Fail,
+                    detail: format!("unable to begin a read transaction - {:?}", err),
+                }]
+            }
+        }
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_saml2_metadata(
+        &self,
+        sp_name: String,
+        eventid: Uuid,
+    ) -> Result<String, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        idms_prox_read.saml2_metadata(&sp_name)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_saml2_sso(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        sp_name: String,
+        saml_request: String,
+        relay_state: Option<String>,
+        eventid: Uuid,
+    ) -> Result<Saml2SsoResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .inspect_err(|e| {
+                error!("Invalid identity: {:?}", e);
+            })
+            .ok();
+
+        idms_prox_read.check_saml2_sso(ident.as_ref(), &sp_name, &saml_request, relay_state, ct)
+    }
+
     pub fn domain_info_read(&self) -> DomainInfoRead {
         self.idms.domain_read()
     }
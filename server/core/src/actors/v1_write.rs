@@ -1,12 +1,18 @@
 use std::iter;
 
-use compact_jwt::JweCompact;
+use compact_jwt::{JweCompact, JwsCompact};
 use kanidm_proto::internal::{
-    CUIntentToken, CUSessionToken, CUStatus, CreateRequest, DeleteRequest, ImageValue,
-    Modify as ProtoModify, ModifyList as ProtoModifyList, ModifyRequest,
+    BulkImportFailure, BulkImportPhase, BulkImportRequest, BulkImportResponse, CUIntentToken,
+    CUIntentTokenInfo, CUSessionToken, CUStatus, CreateRequest, DeleteRequest, DryRunResponse,
+    ImageValue, Modify as ProtoModify, ModifyList as ProtoModifyList, ModifyRequest,
     Oauth2ClaimMapJoin as ProtoOauth2ClaimMapJoin, OperationError,
 };
-use kanidm_proto::v1::{AccountUnixExtend, Entry as ProtoEntry, GroupUnixExtend};
+use kanidm_proto::v1::{
+    AccountUnixExtend, ApiTokenGenerate, Entry as ProtoEntry, EntryApplyResponse,
+    GroupMemberValidUntil, GroupMembershipRequestApprove, GroupMembershipRequestCreate,
+    GroupTemporaryMembers, GroupUnixExtend, HostEnrollRequest, JwtPublicKeyAdd, KerberosKeytab,
+    KerberosTicket, KerberosTicketRequest,
+};
 use std::str::FromStr;
 use time::OffsetDateTime;
 use tracing::{info, instrument, trace};
@@ -16,17 +22,34 @@ use kanidmd_lib::{
     event::{CreateEvent, DeleteEvent, ModifyEvent, ReviveRecycledEvent},
     filter::{Filter, FilterInvalid},
     idm::account::DestroySessionTokenEvent,
+    idm::accountrecovery::{
+        AccountRecoveryBeginEvent, AccountRecoveryVerify, AccountRecoveryVerifyEvent,
+    },
+    idm::apitoken::{DestroySelfApiTokenEvent, GenerateSelfApiTokenEvent},
+    idm::application::{GenerateSelfApplicationPasswordEvent, RevokeSelfApplicationPasswordEvent},
     idm::credupdatesession::{
         CredentialUpdateIntentTokenExchange, CredentialUpdateSessionToken,
         InitCredentialUpdateEvent, InitCredentialUpdateIntentEvent,
+        ListCredentialUpdateIntentEvent, RevokeCredentialUpdateIntentEvent,
     },
-    idm::event::{GeneratePasswordEvent, RegenerateRadiusSecretEvent, UnixPasswordChangeEvent},
+    idm::event::{
+        GeneratePasswordEvent, RadiusCertificateIssueEvent, RegenerateRadiusSecretEvent,
+        SshCertificateIssueEvent, UnixPasswordChangeEvent,
+    },
+    idm::host::{GenerateHostJoinTokenEvent, HostEnrollEvent, HostRotateCredentialEvent},
+    idm::impersonation::ImpersonateEvent,
+    idm::kerberos::{HostKerberosKeytabEvent, HostKerberosTicketEvent},
+    idm::membershiprequest::{GroupMembershipDecisionEvent, GroupMembershipRequestEvent},
     idm::oauth2::{
-        AccessTokenRequest, AccessTokenResponse, AuthorisePermitSuccess, Oauth2Error,
-        TokenRevokeRequest,
+        AccessTokenRequest, AccessTokenResponse, AuthorisePermitSuccess, ClientRegistrationRequest,
+        ClientRegistrationResponse, Oauth2Error, TokenRevokeRequest,
     },
+    idm::oauth2consent::RevokeSelfOauth2ConsentEvent,
     idm::server::IdmServerTransaction,
-    idm::serviceaccount::{DestroyApiTokenEvent, GenerateApiTokenEvent},
+    idm::serviceaccount::{
+        AddJwtPublicKeyEvent, DestroyApiTokenEvent, GenerateApiTokenEvent, RemoveJwtPublicKeyEvent,
+    },
+    idm::sessions::RevokeSelfSessionEvent,
     modify::{Modify, ModifyInvalid, ModifyList},
     value::{OauthClaimMapJoin, PartialValue, Value},
 };
@@ -174,6 +197,86 @@ impl QueryServerWriteV1 {
             .and_then(|_| idms_prox_write.commit())
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_bulk_import(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        req: BulkImportRequest,
+        eventid: Uuid,
+    ) -> Result<BulkImportResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+
+        // Most batches apply cleanly, so first attempt the whole batch in one transaction.
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info.clone(), ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let batch_result = match req.phase {
+            BulkImportPhase::Entries => idms_prox_write
+                .qs_write
+                .import_entries(&ident, &req.entries),
+            BulkImportPhase::References => idms_prox_write
+                .qs_write
+                .import_references(&ident, &req.entries),
+        }
+        .and_then(|_| idms_prox_write.commit());
+
+        match batch_result {
+            Ok(()) => {
+                return Ok(BulkImportResponse {
+                    imported: req.entries.len(),
+                    failures: Vec::with_capacity(0),
+                })
+            }
+            Err(err) => {
+                info!(
+                    ?err,
+                    "Bulk import batch failed, importing each entry separately"
+                );
+            }
+        }
+
+        // Something in the batch failed. Import each entry in its own transaction so that
+        // the failures can be reported, and the remaining entries are still imported.
+        let mut imported = 0;
+        let mut failures = Vec::new();
+
+        for (index, entry) in req.entries.iter().enumerate() {
+            let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+            let ident =
+                idms_prox_write.validate_client_auth_info_to_ident(client_auth_info.clone(), ct)?;
+
+            let entries = std::slice::from_ref(entry);
+
+            let result = match req.phase {
+                BulkImportPhase::Entries => {
+                    idms_prox_write.qs_write.import_entries(&ident, entries)
+                }
+                BulkImportPhase::References => {
+                    idms_prox_write.qs_write.import_references(&ident, entries)
+                }
+            }
+            .and_then(|_| idms_prox_write.commit());
+
+            match result {
+                Ok(()) => imported += 1,
+                Err(error) => failures.push(BulkImportFailure { index, error }),
+            }
+        }
+
+        Ok(BulkImportResponse { imported, failures })
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -245,6 +348,105 @@ impl QueryServerWriteV1 {
             .and_then(|_| idms_prox_write.commit())
     }
 
+    /// Check a create, returning the entries as they would be once created. The transaction
+    /// is never committed.
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_create_dry_run(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        req: CreateRequest,
+        eventid: Uuid,
+    ) -> Result<DryRunResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let crt = CreateEvent::from_message(ident, &req, &mut idms_prox_write.qs_write)
+            .inspect_err(|err| admin_warn!(?err, "Failed to begin create"))?;
+
+        trace!(?crt, "Begin create dry run");
+
+        idms_prox_write
+            .qs_write
+            .create_dry_run(crt)
+            .map(|entries| DryRunResponse { entries })
+    }
+
+    /// Check a modify, returning the entries as they would be after the modification. The
+    /// transaction is never committed.
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_modify_dry_run(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        req: ModifyRequest,
+        eventid: Uuid,
+    ) -> Result<DryRunResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let mdf = ModifyEvent::from_message(ident, &req, &mut idms_prox_write.qs_write)
+            .inspect_err(|err| error!(?err, "Failed to begin modify during dry run"))?;
+
+        trace!(?mdf, "Begin modify dry run");
+
+        idms_prox_write
+            .qs_write
+            .modify_dry_run(&mdf)
+            .map(|entries| DryRunResponse { entries })
+    }
+
+    /// Check a delete, returning the entries that would be deleted. The transaction is never
+    /// committed.
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_delete_dry_run(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        req: DeleteRequest,
+        eventid: Uuid,
+    ) -> Result<DryRunResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let del = DeleteEvent::from_message(ident, &req, &mut idms_prox_write.qs_write)
+            .inspect_err(|err| error!(?err, "Failed to begin delete"))?;
+
+        trace!(?del, "Begin delete dry run");
+
+        idms_prox_write
+            .qs_write
+            .delete_dry_run(&del)
+            .map(|entries| DryRunResponse { entries })
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -324,6 +526,75 @@ impl QueryServerWriteV1 {
             .and_then(|_| idms_prox_write.commit().map(|_| ()))
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_entry_apply(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        id: String,
+        filter: Filter<FilterInvalid>,
+        entry: ProtoEntry,
+        if_match: Option<String>,
+        dry_run: bool,
+        eventid: Uuid,
+    ) -> Result<EntryApplyResponse, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let response = idms_prox_write.qs_write.apply_entry(
+            &ident,
+            &id,
+            filter,
+            &entry,
+            if_match.as_deref(),
+            dry_run,
+        )?;
+
+        // A dry run is checked in full, but the transaction is dropped rather than committed.
+        if !dry_run {
+            idms_prox_write.commit()?;
+        }
+
+        Ok(response)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_entry_delete_if_match(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        id: String,
+        filter: Filter<FilterInvalid>,
+        if_match: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        idms_prox_write
+            .qs_write
+            .delete_entry_if_match(&ident, &id, filter, &if_match)
+            .and_then(|_| idms_prox_write.commit())
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -413,9 +684,7 @@ impl QueryServerWriteV1 {
         &self,
         client_auth_info: ClientAuthInfo,
         uuid_or_name: String,
-        label: String,
-        expiry: Option<OffsetDateTime>,
-        read_write: bool,
+        request: ApiTokenGenerate,
         eventid: Uuid,
     ) -> Result<String, OperationError> {
         let ct = duration_from_epoch_now();
@@ -435,12 +704,20 @@ impl QueryServerWriteV1 {
                 e
             })?;
 
+        let ApiTokenGenerate {
+            label,
+            expiry,
+            read_write,
+            permissions,
+        } = request;
+
         let gte = GenerateApiTokenEvent {
             ident,
             target,
             label,
             expiry,
             read_write,
+            permissions: permissions.iter().map(|p| p.into()).collect(),
         };
 
         idms_prox_write
@@ -494,11 +771,11 @@ impl QueryServerWriteV1 {
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_account_user_auth_token_destroy(
+    pub async fn handle_service_account_jwt_public_key_add(
         &self,
         client_auth_info: ClientAuthInfo,
         uuid_or_name: String,
-        token_id: Uuid,
+        request: JwtPublicKeyAdd,
         eventid: Uuid,
     ) -> Result<(), OperationError> {
         let ct = duration_from_epoch_now();
@@ -518,14 +795,15 @@ impl QueryServerWriteV1 {
                 e
             })?;
 
-        let dte = DestroySessionTokenEvent {
+        let ake = AddJwtPublicKeyEvent {
             ident,
             target,
-            token_id,
+            label: request.label,
+            public_key_pem: request.public_key,
         };
 
         idms_prox_write
-            .account_destroy_session_token(&dte)
+            .service_account_add_jwt_public_key(&ake)
             .and_then(|r| idms_prox_write.commit().map(|_| r))
     }
 
@@ -534,49 +812,38 @@ impl QueryServerWriteV1 {
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_logout(
+    pub async fn handle_service_account_jwt_public_key_remove(
         &self,
         client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        label: String,
         eventid: Uuid,
     ) -> Result<(), OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
 
-        // We specifically need a uat here to assess the auth type!
-        let validate_result =
-            idms_prox_write.validate_client_auth_info_to_ident(client_auth_info, ct);
-
-        let ident = match validate_result {
-            Ok(ident) => ident,
-            Err(OperationError::SessionExpired) | Err(OperationError::NotAuthenticated) => {
-                return Ok(())
-            }
-            Err(err) => {
-                error!(?err, "Invalid identity");
-                return Err(err);
-            }
-        };
-
-        if !ident.can_logout() {
-            info!("Ignoring request to logout session - these sessions are not recorded");
-            return Ok(());
-        };
-
-        let target = ident.get_uuid().ok_or_else(|| {
-            error!("Invalid identity - no uuid present");
-            OperationError::InvalidState
-        })?;
-
-        let token_id = ident.get_session_id();
+        let target = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
 
-        let dte = DestroySessionTokenEvent {
+        let rke = RemoveJwtPublicKeyEvent {
             ident,
             target,
-            token_id,
+            label,
         };
 
         idms_prox_write
-            .account_destroy_session_token(&dte)
+            .service_account_remove_jwt_public_key(&rke)
             .and_then(|r| idms_prox_write.commit().map(|_| r))
     }
 
@@ -585,12 +852,14 @@ impl QueryServerWriteV1 {
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_idmcredentialupdate(
+    pub async fn handle_self_api_token_generate(
         &self,
         client_auth_info: ClientAuthInfo,
-        uuid_or_name: String,
+        label: String,
+        expiry: Option<OffsetDateTime>,
+        read_write: bool,
         eventid: Uuid,
-    ) -> Result<(CUSessionToken, CUStatus), OperationError> {
+    ) -> Result<String, OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await?;
         let ident = idms_prox_write
@@ -600,46 +869,171 @@ impl QueryServerWriteV1 {
                 e
             })?;
 
-        let target_uuid = idms_prox_write
-            .qs_write
-            .name_to_uuid(uuid_or_name.as_str())
-            .map_err(|e| {
-                error!(err = ?e, "Error resolving id to target");
-                e
-            })?;
+        let gte = GenerateSelfApiTokenEvent {
+            ident,
+            label,
+            expiry,
+            read_write,
+        };
 
         idms_prox_write
-            .init_credential_update(&InitCredentialUpdateEvent::new(ident, target_uuid), ct)
-            .and_then(|tok| idms_prox_write.commit().map(|_| tok))
-            .map_err(|e| {
-                error!(
-                    err = ?e,
-                    "Failed to begin init_credential_update",
-                );
-                e
-            })
-            .map(|(tok, sta)| {
-                (
-                    CUSessionToken {
-                        token: tok.token_enc.to_string(),
-                    },
-                    sta.into(),
-                )
-            })
+            .account_generate_self_api_token(&gte, ct)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+            .map(|token| token.to_string())
     }
 
     #[instrument(
         level = "info",
         skip_all,
-        fields(uuid = ?eventid),
+        fields(uuid = ?eventid)
     )]
-    pub async fn handle_idmcredentialupdateintent(
+    pub async fn handle_self_api_token_destroy(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        token_id: Uuid,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let dte = DestroySelfApiTokenEvent { ident, token_id };
+
+        idms_prox_write
+            .account_destroy_self_api_token(&dte)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_self_application_password_generate(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        application: String,
+        label: String,
+        eventid: Uuid,
+    ) -> Result<String, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let gae = GenerateSelfApplicationPasswordEvent {
+            ident,
+            application,
+            label,
+        };
+
+        idms_prox_write
+            .generate_self_application_password(&gae)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_self_application_password_revoke(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        password_id: Uuid,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let rae = RevokeSelfApplicationPasswordEvent { ident, password_id };
+
+        idms_prox_write
+            .revoke_self_application_password(&rae)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_self_oauth2_consent_revoke(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        client_name: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let rce = RevokeSelfOauth2ConsentEvent { ident, client_name };
+
+        idms_prox_write
+            .revoke_self_oauth2_consent(&rce)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_self_session_revoke(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        session_id: Uuid,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let rse = RevokeSelfSessionEvent { ident, session_id };
+
+        idms_prox_write
+            .revoke_self_session(&rse)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_account_user_auth_token_destroy(
         &self,
         client_auth_info: ClientAuthInfo,
         uuid_or_name: String,
-        ttl: Option<Duration>,
+        token_id: Uuid,
         eventid: Uuid,
-    ) -> Result<CUIntentToken, OperationError> {
+    ) -> Result<(), OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await?;
         let ident = idms_prox_write
@@ -649,7 +1043,7 @@ impl QueryServerWriteV1 {
                 e
             })?;
 
-        let target_uuid = idms_prox_write
+        let target = idms_prox_write
             .qs_write
             .name_to_uuid(uuid_or_name.as_str())
             .map_err(|e| {
@@ -657,23 +1051,56 @@ impl QueryServerWriteV1 {
                 e
             })?;
 
+        let dte = DestroySessionTokenEvent {
+            ident,
+            target,
+            token_id,
+        };
+
         idms_prox_write
-            .init_credential_update_intent(
-                &InitCredentialUpdateIntentEvent::new(ident, target_uuid, ttl),
-                ct,
-            )
-            .and_then(|tok| idms_prox_write.commit().map(|_| tok))
+            .account_destroy_session_token(&dte)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_impersonate_session(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        expiry: Option<u64>,
+        eventid: Uuid,
+    ) -> Result<String, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
             .map_err(|e| {
-                error!(
-                    err = ?e,
-                    "Failed to begin init_credential_update_intent",
-                );
+                error!(err = ?e, "Invalid identity");
                 e
-            })
-            .map(|tok| CUIntentToken {
-                token: tok.intent_id,
-                expiry_time: tok.expiry_time,
-            })
+            })?;
+
+        let target = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        let ie = ImpersonateEvent {
+            ident,
+            target,
+            expiry: expiry.map(Duration::from_secs),
+        };
+
+        idms_prox_write
+            .impersonate_session(&ie, ct)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+            .map(|token| token.to_string())
     }
 
     #[instrument(
@@ -681,17 +1108,416 @@ impl QueryServerWriteV1 {
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_idmcredentialexchangeintent(
+    pub async fn handle_logout(
         &self,
-        intent_id: String,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        // We specifically need a uat here to assess the auth type!
+        let validate_result =
+            idms_prox_write.validate_client_auth_info_to_ident(client_auth_info, ct);
+
+        let ident = match validate_result {
+            Ok(ident) => ident,
+            Err(OperationError::SessionExpired) | Err(OperationError::NotAuthenticated) => {
+                return Ok(())
+            }
+            Err(err) => {
+                error!(?err, "Invalid identity");
+                return Err(err);
+            }
+        };
+
+        if !ident.can_logout() {
+            info!("Ignoring request to logout session - these sessions are not recorded");
+            return Ok(());
+        };
+
+        let target = ident.get_uuid().ok_or_else(|| {
+            error!("Invalid identity - no uuid present");
+            OperationError::InvalidState
+        })?;
+
+        let token_id = ident.get_session_id();
+
+        let dte = DestroySessionTokenEvent {
+            ident,
+            target,
+            token_id,
+        };
+
+        idms_prox_write
+            .account_destroy_session_token(&dte)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmcredentialupdate(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
         eventid: Uuid,
     ) -> Result<(CUSessionToken, CUStatus), OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await?;
-        let intent_token = CredentialUpdateIntentTokenExchange { intent_id };
-        // TODO: this is throwing a 500 error when a session is already in use, that seems bad?
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let target_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
         idms_prox_write
-            .exchange_intent_credential_update(intent_token, ct)
+            .init_credential_update(&InitCredentialUpdateEvent::new(ident, target_uuid), ct)
+            .and_then(|tok| idms_prox_write.commit().map(|_| tok))
+            .map_err(|e| {
+                error!(
+                    err = ?e,
+                    "Failed to begin init_credential_update",
+                );
+                e
+            })
+            .map(|(tok, sta)| {
+                (
+                    CUSessionToken {
+                        token: tok.token_enc.to_string(),
+                    },
+                    sta.into(),
+                )
+            })
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid),
+    )]
+    pub async fn handle_idmcredentialupdateintent(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        ttl: Option<Duration>,
+        max_uses: Option<u32>,
+        send_mail: bool,
+        eventid: Uuid,
+    ) -> Result<CUIntentToken, OperationError> {
+        let mailer = if send_mail {
+            Some(
+                self.mailer
+                    .clone()
+                    .ok_or(OperationError::KG004MailNotConfigured)?,
+            )
+        } else {
+            None
+        };
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let target_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        let tok = idms_prox_write
+            .init_credential_update_intent(
+                &InitCredentialUpdateIntentEvent::new(ident, target_uuid, ttl)
+                    .with_max_uses(max_uses),
+                ct,
+            )
+            .and_then(|tok| {
+                // Don't issue a token that can't be delivered.
+                if send_mail && tok.mail_primary.is_none() {
+                    return Err(OperationError::CU0008IntentTokenNoMail);
+                }
+                idms_prox_write.commit().map(|_| tok)
+            })
+            .map_err(|e| {
+                error!(
+                    err = ?e,
+                    "Failed to begin init_credential_update_intent",
+                );
+                e
+            })?;
+
+        let mailed_to = match (mailer, tok.mail_primary.as_deref()) {
+            (Some(mailer), Some(mail)) => {
+                let branding = mailer.branding(&self.idms.domain_read());
+                mailer
+                    .send_credential_reset(&branding, &tok, mail)
+                    .await
+                    .inspect_err(|err| {
+                        error!(?err, "Failed to send credential reset token");
+                    })?;
+                Some(mail.to_string())
+            }
+            _ => None,
+        };
+
+        Ok(CUIntentToken {
+            token: tok.intent_id,
+            expiry_time: tok.expiry_time,
+            max_uses: tok.max_uses,
+            mailed_to,
+        })
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid),
+    )]
+    pub async fn handle_idmcredentialupdateintent_list(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<Vec<CUIntentTokenInfo>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let target_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        // Nothing is changed, so the transaction is not committed.
+        idms_prox_write
+            .list_credential_update_intents(
+                &ListCredentialUpdateIntentEvent::new(ident, target_uuid),
+                ct,
+            )
+            .map(|intents| {
+                intents
+                    .into_iter()
+                    .map(|intent| CUIntentTokenInfo {
+                        token_ref: intent.token_ref,
+                        expiry_time: intent.expiry_time,
+                        state: intent.state,
+                    })
+                    .collect()
+            })
+            .map_err(|e| {
+                error!(err = ?e, "Failed to list credential update intents");
+                e
+            })
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid),
+    )]
+    pub async fn handle_idmcredentialupdateintent_revoke(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        token_ref: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let target_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        idms_prox_write
+            .revoke_credential_update_intent(&RevokeCredentialUpdateIntentEvent::new(
+                ident,
+                target_uuid,
+                token_ref,
+            ))
+            .and_then(|_| idms_prox_write.commit())
+            .map_err(|e| {
+                error!(err = ?e, "Failed to revoke credential update intent");
+                e
+            })
+    }
+
+    /// Account recovery is only available when mail can be sent.
+    pub fn account_recovery_enabled(&self) -> bool {
+        self.mailer.is_some()
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_account_recovery_begin(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        name: String,
+        eventid: Uuid,
+    ) -> Result<Uuid, OperationError> {
+        let mailer = self
+            .mailer
+            .clone()
+            .ok_or(OperationError::KG004MailNotConfigured)?;
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ev = AccountRecoveryBeginEvent::new(client_auth_info.source, name.as_str());
+
+        let begin = idms_prox_write
+            .account_recovery_begin(&ev, ct)
+            .and_then(|begin| idms_prox_write.commit().map(|_| begin))
+            .map_err(|e| {
+                error!(err = ?e, "Failed to begin account recovery");
+                e
+            })?;
+
+        if let Some(message) = begin.message {
+            // Send in the background so that the time taken to respond does not reveal
+            // whether a message was sent. For the same reason, failures are only logged.
+            let branding = mailer.branding(&self.idms.domain_read());
+            tokio::spawn(async move {
+                if let Err(err) = mailer.send_account_recovery(&branding, &message).await {
+                    error!(?err, "Failed to send account recovery code");
+                }
+            });
+        }
+
+        Ok(begin.recovery_id)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_account_recovery_verify(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        recovery_id: Uuid,
+        code: String,
+        eventid: Uuid,
+    ) -> Result<(CUSessionToken, CUStatus), OperationError> {
+        if self.mailer.is_none() {
+            return Err(OperationError::KG004MailNotConfigured);
+        }
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ev =
+            AccountRecoveryVerifyEvent::new(client_auth_info.source, recovery_id, code.as_str());
+
+        // Failed attempts must be committed so that they are counted.
+        let result = idms_prox_write
+            .account_recovery_verify(&ev, ct)
+            .and_then(|result| idms_prox_write.commit().map(|_| result))
+            .map_err(|e| {
+                error!(err = ?e, "Failed to verify account recovery");
+                e
+            })?;
+
+        match result {
+            AccountRecoveryVerify::Success(tok, sta) => Ok((
+                CUSessionToken {
+                    token: tok.token_enc.to_string(),
+                },
+                sta.into(),
+            )),
+            AccountRecoveryVerify::InvalidCode => Err(OperationError::AccessDenied),
+            AccountRecoveryVerify::Expired => Err(OperationError::SessionExpired),
+        }
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_password_expired_exchange(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        token: String,
+        eventid: Uuid,
+    ) -> Result<(CUSessionToken, CUStatus), OperationError> {
+        let token = JwsCompact::from_str(token.as_str()).map_err(|err| {
+            error!(?err, "Failed to parse password expired token");
+            OperationError::NotAuthenticated
+        })?;
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        idms_prox_write
+            .password_expired_exchange(&token, client_auth_info.source, ct)
+            .and_then(|tok| idms_prox_write.commit().map(|_| tok))
+            .map_err(|e| {
+                error!(err = ?e, "Failed to begin credential update for an expired password");
+                e
+            })
+            .map(|(tok, sta)| {
+                (
+                    CUSessionToken {
+                        token: tok.token_enc.to_string(),
+                    },
+                    sta.into(),
+                )
+            })
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmcredentialexchangeintent(
+        &self,
+        intent_id: String,
+        eventid: Uuid,
+    ) -> Result<(CUSessionToken, CUStatus), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let intent_token = CredentialUpdateIntentTokenExchange { intent_id };
+        // TODO: this is throwing a 500 error when a session is already in use, that seems bad?
+        idms_prox_write
+            .exchange_intent_credential_update(intent_token, ct)
             .and_then(|tok| idms_prox_write.commit().map(|_| tok))
             .map_err(|e| {
                 error!(
@@ -715,31 +1541,308 @@ impl QueryServerWriteV1 {
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_idmcredentialupdatecommit(
+    pub async fn handle_idmcredentialupdatecommit(
+        &self,
+        session_token: CUSessionToken,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let session_token = JweCompact::from_str(session_token.token.as_str())
+            .map(|token_enc| CredentialUpdateSessionToken { token_enc })
+            .map_err(|err| {
+                error!(?err, "malformed token");
+                OperationError::InvalidRequestState
+            })?;
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        idms_prox_write
+            .commit_credential_update(&session_token, ct)
+            .and_then(|tok| idms_prox_write.commit().map(|_| tok))
+            .map_err(|e| {
+                error!(
+                    err = ?e,
+                    "Failed to begin commit_credential_update",
+                );
+                e
+            })
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmcredentialupdatecancel(
+        &self,
+        session_token: CUSessionToken,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let session_token = JweCompact::from_str(session_token.token.as_str())
+            .map(|token_enc| CredentialUpdateSessionToken { token_enc })
+            .map_err(|err| {
+                error!(?err, "malformed token");
+                OperationError::InvalidRequestState
+            })?;
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        idms_prox_write
+            .cancel_credential_update(&session_token, ct)
+            .and_then(|tok| idms_prox_write.commit().map(|_| tok))
+            .map_err(|e| {
+                error!(
+                    err = ?e,
+                    "Failed to begin commit_credential_cancel",
+                );
+                e
+            })
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_service_account_into_person(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+        let target_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        idms_prox_write
+            .service_account_into_person(&ident, target_uuid)
+            .and_then(|_| idms_prox_write.commit())
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_regenerateradius(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<String, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let target_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        let rrse = RegenerateRadiusSecretEvent::from_parts(
+            // &idms_prox_write.qs_write,
+            ident,
+            target_uuid,
+        )
+        .map_err(|e| {
+            error!(
+                err = ?e,
+                "Failed to begin idm_account_regenerate_radius",
+            );
+            e
+        })?;
+
+        idms_prox_write
+            .regenerate_radius_secret(&rrse)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_radiuscertificateissue(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        csr: String,
+        eventid: Uuid,
+    ) -> Result<String, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let target_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        let rcie = RadiusCertificateIssueEvent::from_parts(ident, target_uuid, csr).map_err(|e| {
+            error!(
+                err = ?e,
+                "Failed to begin idm_account_radius_certificate_issue",
+            );
+            e
+        })?;
+
+        idms_prox_write
+            .issue_radius_certificate(&rcie)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_radiuscacertificate(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<String, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        // Any authenticated identity may view the certificate authority.
+        let _ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        // The certificate authority is created on first use, so this must commit.
+        idms_prox_write
+            .radius_ca_certificate_pem()
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_sshcertificateissue(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        public_key: String,
+        eventid: Uuid,
+    ) -> Result<String, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let scie = SshCertificateIssueEvent::from_parts(ident, public_key).map_err(|e| {
+            error!(
+                err = ?e,
+                "Failed to begin idm_ssh_certificate_issue",
+            );
+            e
+        })?;
+
+        idms_prox_write
+            .issue_ssh_certificate(&scie, ct)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_sshcapublickeys(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+    ) -> Result<Vec<String>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        // Any authenticated identity may view the certificate authorities.
+        let _ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        // The certificate authority is created on first use, so this must commit.
+        idms_prox_write
+            .ssh_ca_public_keys()
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_host_join_token_generate(
         &self,
-        session_token: CUSessionToken,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        ttl: Option<u64>,
         eventid: Uuid,
-    ) -> Result<(), OperationError> {
-        let session_token = JweCompact::from_str(session_token.token.as_str())
-            .map(|token_enc| CredentialUpdateSessionToken { token_enc })
-            .map_err(|err| {
-                error!(?err, "malformed token");
-                OperationError::InvalidRequestState
-            })?;
-
+    ) -> Result<String, OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
 
-        idms_prox_write
-            .commit_credential_update(&session_token, ct)
-            .and_then(|tok| idms_prox_write.commit().map(|_| tok))
+        let target = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
             .map_err(|e| {
-                error!(
-                    err = ?e,
-                    "Failed to begin commit_credential_update",
-                );
+                error!(err = ?e, "Error resolving id to target");
                 e
-            })
+            })?;
+
+        let gjte = GenerateHostJoinTokenEvent {
+            ident,
+            target,
+            ttl: ttl.map(Duration::from_secs),
+        };
+
+        idms_prox_write
+            .host_generate_join_token(&gjte, ct)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
     }
 
     #[instrument(
@@ -747,31 +1850,23 @@ impl QueryServerWriteV1 {
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_idmcredentialupdatecancel(
+    pub async fn handle_host_enroll(
         &self,
-        session_token: CUSessionToken,
+        request: HostEnrollRequest,
         eventid: Uuid,
-    ) -> Result<(), OperationError> {
-        let session_token = JweCompact::from_str(session_token.token.as_str())
-            .map(|token_enc| CredentialUpdateSessionToken { token_enc })
-            .map_err(|err| {
-                error!(?err, "malformed token");
-                OperationError::InvalidRequestState
-            })?;
-
+    ) -> Result<String, OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        // The join token authenticates the machine, so no identity is required.
+        let hee = HostEnrollEvent {
+            name: request.name,
+            join_token: request.join_token,
+        };
 
         idms_prox_write
-            .cancel_credential_update(&session_token, ct)
-            .and_then(|tok| idms_prox_write.commit().map(|_| tok))
-            .map_err(|e| {
-                error!(
-                    err = ?e,
-                    "Failed to begin commit_credential_cancel",
-                );
-                e
-            })
+            .host_enroll(&hee, ct)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+            .map(|token| token.to_string())
     }
 
     #[instrument(
@@ -779,12 +1874,11 @@ impl QueryServerWriteV1 {
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_service_account_into_person(
+    pub async fn handle_host_credential_rotate(
         &self,
         client_auth_info: ClientAuthInfo,
-        uuid_or_name: String,
         eventid: Uuid,
-    ) -> Result<(), OperationError> {
+    ) -> Result<String, OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await?;
         let ident = idms_prox_write
@@ -793,17 +1887,13 @@ impl QueryServerWriteV1 {
                 error!(err = ?e, "Invalid identity");
                 e
             })?;
-        let target_uuid = idms_prox_write
-            .qs_write
-            .name_to_uuid(uuid_or_name.as_str())
-            .map_err(|e| {
-                error!(err = ?e, "Error resolving id to target");
-                e
-            })?;
+
+        let hrce = HostRotateCredentialEvent { ident };
 
         idms_prox_write
-            .service_account_into_person(&ident, target_uuid)
-            .and_then(|_| idms_prox_write.commit())
+            .host_rotate_credential(&hrce, ct)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+            .map(|token| token.to_string())
     }
 
     #[instrument(
@@ -811,12 +1901,11 @@ impl QueryServerWriteV1 {
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_regenerateradius(
+    pub async fn handle_host_kerberos_keytab(
         &self,
         client_auth_info: ClientAuthInfo,
-        uuid_or_name: String,
         eventid: Uuid,
-    ) -> Result<String, OperationError> {
+    ) -> Result<KerberosKeytab, OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await?;
         let ident = idms_prox_write
@@ -826,29 +1915,41 @@ impl QueryServerWriteV1 {
                 e
             })?;
 
-        let target_uuid = idms_prox_write
-            .qs_write
-            .name_to_uuid(uuid_or_name.as_str())
+        let hkke = HostKerberosKeytabEvent { ident };
+
+        idms_prox_write
+            .host_kerberos_keytab(&hkke)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_host_kerberos_ticket(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        request: KerberosTicketRequest,
+        eventid: Uuid,
+    ) -> Result<KerberosTicket, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
             .map_err(|e| {
-                error!(err = ?e, "Error resolving id to target");
+                error!(err = ?e, "Invalid identity");
                 e
             })?;
 
-        let rrse = RegenerateRadiusSecretEvent::from_parts(
-            // &idms_prox_write.qs_write,
+        let hkte = HostKerberosTicketEvent {
             ident,
-            target_uuid,
-        )
-        .map_err(|e| {
-            error!(
-                err = ?e,
-                "Failed to begin idm_account_regenerate_radius",
-            );
-            e
-        })?;
+            account_id: request.account_id,
+            service: request.service,
+        };
 
         idms_prox_write
-            .regenerate_radius_secret(&rrse)
+            .host_kerberos_ticket(&hkte, ct)
             .and_then(|r| idms_prox_write.commit().map(|_| r))
     }
 
@@ -1041,98 +2142,268 @@ impl QueryServerWriteV1 {
         // than relying on the proto ones.
         let ml = ModifyList::new_append(Attribute::SshPublicKey, v_sk);
 
-        self.modify_from_internal_parts(client_auth_info, &uuid_or_name, &ml, filter)
-            .await
+        self.modify_from_internal_parts(client_auth_info, &uuid_or_name, &ml, filter)
+            .await
+    }
+
+    #[instrument(
+        level = "info",
+        name = "idm_account_unix_extend",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmaccountunixextend(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        ux: AccountUnixExtend,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let AccountUnixExtend { gidnumber, shell } = ux;
+        // The filter_map here means we only create the mods if the gidnumber or shell are set
+        // in the actual request.
+        let mods: Vec<_> = iter::once(Some(Modify::Present(
+            Attribute::Class,
+            EntryClass::PosixAccount.into(),
+        )))
+        .chain(iter::once(
+            gidnumber
+                .as_ref()
+                .map(|_| Modify::Purged(Attribute::GidNumber)),
+        ))
+        .chain(iter::once(gidnumber.map(|n| {
+            Modify::Present(Attribute::GidNumber, Value::new_uint32(n))
+        })))
+        .chain(iter::once(
+            shell
+                .as_ref()
+                .map(|_| Modify::Purged(Attribute::LoginShell)),
+        ))
+        .chain(iter::once(shell.map(|s| {
+            Modify::Present(Attribute::LoginShell, Value::new_iutf8(s.as_str()))
+        })))
+        .flatten()
+        .collect();
+
+        let ml = ModifyList::new_list(mods);
+
+        let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Account.into()));
+
+        self.modify_from_internal_parts(client_auth_info, &uuid_or_name, &ml, filter)
+            .await
+    }
+
+    #[instrument(
+        level = "info",
+        name = "idm_group_unix_extend",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_idmgroupunixextend(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        gx: GroupUnixExtend,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        // The if let Some here means we only create the mods if the gidnumber is set
+        // in the actual request.
+
+        let gidnumber_mods = if let Some(gid) = gx.gidnumber {
+            [
+                Some(Modify::Purged(Attribute::GidNumber)),
+                Some(Modify::Present(
+                    Attribute::GidNumber,
+                    Value::new_uint32(gid),
+                )),
+            ]
+        } else {
+            [None, None]
+        };
+        let mods: Vec<_> = iter::once(Some(Modify::Present(
+            Attribute::Class,
+            EntryClass::PosixGroup.into(),
+        )))
+        .chain(gidnumber_mods)
+        .flatten()
+        .collect();
+
+        let ml = ModifyList::new_list(mods);
+
+        let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()));
+
+        self.modify_from_internal_parts(client_auth_info, &uuid_or_name, &ml, filter)
+            .await
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_group_add_temporary_members(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        request: GroupTemporaryMembers,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        if request.valid_until <= OffsetDateTime::UNIX_EPOCH + ct {
+            error!("Temporary group members must have a valid until time in the future");
+            return Err(OperationError::InvalidRequestState);
+        }
+
+        let group_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
+
+        let member_uuids = request
+            .members
+            .iter()
+            .map(|member| idms_prox_write.qs_write.name_to_uuid(member.as_str()))
+            .collect::<Result<BTreeSet<_>, _>>()
+            .inspect_err(|err| {
+                error!(?err, "Error resolving member name to target");
+            })?;
+
+        // Any existing valid until time of these members is replaced.
+        let group = idms_prox_write.qs_write.internal_search_uuid(group_uuid)?;
+
+        let mods = group
+            .get_ava_set(Attribute::MemberValidUntil)
+            .and_then(|vs| vs.as_utf8_iter())
+            .into_iter()
+            .flatten()
+            .filter(|value| {
+                GroupMemberValidUntil::from_str(value)
+                    .is_ok_and(|valid_until| member_uuids.contains(&valid_until.member))
+            })
+            .map(|value| {
+                Modify::Removed(Attribute::MemberValidUntil, PartialValue::new_utf8s(value))
+            })
+            .chain(member_uuids.iter().flat_map(|member| {
+                [
+                    Modify::Present(Attribute::Member, Value::Refer(*member)),
+                    Modify::Present(
+                        Attribute::MemberValidUntil,
+                        Value::new_utf8(
+                            GroupMemberValidUntil {
+                                member: *member,
+                                valid_until: request.valid_until,
+                            }
+                            .to_string(),
+                        ),
+                    ),
+                ]
+            }))
+            .collect();
+
+        let ml = ModifyList::new_list(mods);
+
+        let filter = filter_all!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Group.into()),
+            f_eq(Attribute::Uuid, PartialValue::Uuid(group_uuid))
+        ]));
+
+        let mdf = ModifyEvent::from_internal_parts(ident, &ml, &filter, &idms_prox_write.qs_write)
+            .inspect_err(|err| {
+                error!(?err, "Failed to begin modify");
+            })?;
+
+        trace!(?mdf, "Begin modify event");
+
+        idms_prox_write
+            .qs_write
+            .modify(&mdf)
+            .and_then(|_| idms_prox_write.commit().map(|_| ()))
     }
 
     #[instrument(
         level = "info",
-        name = "idm_account_unix_extend",
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_idmaccountunixextend(
+    pub async fn handle_group_membership_request(
         &self,
         client_auth_info: ClientAuthInfo,
         uuid_or_name: String,
-        ux: AccountUnixExtend,
+        request: GroupMembershipRequestCreate,
         eventid: Uuid,
-    ) -> Result<(), OperationError> {
-        let AccountUnixExtend { gidnumber, shell } = ux;
-        // The filter_map here means we only create the mods if the gidnumber or shell are set
-        // in the actual request.
-        let mods: Vec<_> = iter::once(Some(Modify::Present(
-            Attribute::Class,
-            EntryClass::PosixAccount.into(),
-        )))
-        .chain(iter::once(
-            gidnumber
-                .as_ref()
-                .map(|_| Modify::Purged(Attribute::GidNumber)),
-        ))
-        .chain(iter::once(gidnumber.map(|n| {
-            Modify::Present(Attribute::GidNumber, Value::new_uint32(n))
-        })))
-        .chain(iter::once(
-            shell
-                .as_ref()
-                .map(|_| Modify::Purged(Attribute::LoginShell)),
-        ))
-        .chain(iter::once(shell.map(|s| {
-            Modify::Present(Attribute::LoginShell, Value::new_iutf8(s.as_str()))
-        })))
-        .flatten()
-        .collect();
+    ) -> Result<Uuid, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
 
-        let ml = ModifyList::new_list(mods);
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
 
-        let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Account.into()));
+        let group = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
 
-        self.modify_from_internal_parts(client_auth_info, &uuid_or_name, &ml, filter)
-            .await
+        let gmre = GroupMembershipRequestEvent {
+            ident,
+            group,
+            justification: request.justification,
+        };
+
+        idms_prox_write
+            .request_group_membership(&gmre, ct)
+            .and_then(|request_id| idms_prox_write.commit().map(|_| request_id))
     }
 
     #[instrument(
         level = "info",
-        name = "idm_group_unix_extend",
         skip_all,
         fields(uuid = ?eventid)
     )]
-    pub async fn handle_idmgroupunixextend(
+    pub async fn handle_group_membership_request_decide(
         &self,
         client_auth_info: ClientAuthInfo,
-        uuid_or_name: String,
-        gx: GroupUnixExtend,
+        request_id: Uuid,
+        approve: Option<GroupMembershipRequestApprove>,
         eventid: Uuid,
     ) -> Result<(), OperationError> {
-        // The if let Some here means we only create the mods if the gidnumber is set
-        // in the actual request.
-
-        let gidnumber_mods = if let Some(gid) = gx.gidnumber {
-            [
-                Some(Modify::Purged(Attribute::GidNumber)),
-                Some(Modify::Present(
-                    Attribute::GidNumber,
-                    Value::new_uint32(gid),
-                )),
-            ]
-        } else {
-            [None, None]
-        };
-        let mods: Vec<_> = iter::once(Some(Modify::Present(
-            Attribute::Class,
-            EntryClass::PosixGroup.into(),
-        )))
-        .chain(gidnumber_mods)
-        .flatten()
-        .collect();
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
 
-        let ml = ModifyList::new_list(mods);
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
 
-        let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()));
+        // A request is approved when the terms of the approval are given, otherwise it's denied.
+        let gmde = GroupMembershipDecisionEvent {
+            ident,
+            request_id,
+            approve: approve.is_some(),
+            valid_until: approve.and_then(|approve| approve.valid_until),
+        };
 
-        self.modify_from_internal_parts(client_auth_info, &uuid_or_name, &ml, filter)
-            .await
+        idms_prox_write
+            .decide_group_membership_request(&gmde, ct)
+            .and_then(|_| idms_prox_write.commit())
     }
 
     #[instrument(
@@ -1334,6 +2605,120 @@ impl QueryServerWriteV1 {
             .and_then(|_| idms_prox_write.commit().map(|_| ()))
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_saml2_group_map_update(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        group: String,
+        values: Vec<String>,
+        filter: Filter<FilterInvalid>,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        // Because this is from internal, we can generate a real modlist, rather
+        // than relying on the proto ones.
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let group_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(group.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving group name to target");
+                e
+            })?;
+
+        let ml = ModifyList::new_append(
+            Attribute::Saml2GroupMap,
+            Value::new_oauthscopemap(group_uuid, values.into_iter().collect()).ok_or_else(
+                || OperationError::InvalidAttribute("Invalid SAML Group Map syntax".to_string()),
+            )?,
+        );
+
+        let mdf = match ModifyEvent::from_internal_parts(
+            ident,
+            &ml,
+            &filter,
+            &idms_prox_write.qs_write,
+        ) {
+            Ok(m) => m,
+            Err(e) => {
+                error!(err = ?e, "Failed to begin modify");
+                return Err(e);
+            }
+        };
+
+        trace!(?mdf, "Begin modify event");
+
+        idms_prox_write
+            .qs_write
+            .modify(&mdf)
+            .and_then(|_| idms_prox_write.commit().map(|_| ()))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_saml2_group_map_delete(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        group: String,
+        filter: Filter<FilterInvalid>,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let group_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid(group.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving group name to target");
+                e
+            })?;
+
+        let ml = ModifyList::new_remove(Attribute::Saml2GroupMap, PartialValue::Refer(group_uuid));
+
+        let mdf = match ModifyEvent::from_internal_parts(
+            ident,
+            &ml,
+            &filter,
+            &idms_prox_write.qs_write,
+        ) {
+            Ok(m) => m,
+            Err(e) => {
+                error!(err = ?e, "Failed to begin modify");
+                return Err(e);
+            }
+        };
+
+        trace!(?mdf, "Begin modify event");
+
+        idms_prox_write
+            .qs_write
+            .modify(&mdf)
+            .and_then(|_| idms_prox_write.commit().map(|_| ()))
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1628,6 +3013,38 @@ impl QueryServerWriteV1 {
             .and_then(|_| idms_prox_write.commit().map(|_| ()))
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_oauth2_basic_secret_rotate(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        filter: Filter<FilterInvalid>,
+        previous_secret_validity: Option<u64>,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        idms_prox_write
+            .oauth2_basic_secret_rotate(
+                &ident,
+                &filter,
+                previous_secret_validity.map(Duration::from_secs),
+                ct,
+            )
+            .and_then(|()| idms_prox_write.commit())
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1683,6 +3100,40 @@ impl QueryServerWriteV1 {
         resp
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_oauth2_client_register(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        reg_req: ClientRegistrationRequest,
+        eventid: Uuid,
+    ) -> Result<ClientRegistrationResponse, Oauth2Error> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self
+            .idms
+            .proxy_write(ct)
+            .await
+            .map_err(Oauth2Error::ServerError)?;
+
+        // The initial access token of rfc7591 is the bearer token of the caller.
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                Oauth2Error::AuthenticationRequired
+            })?;
+
+        let resp = idms_prox_write.oauth2_client_register(&ident, &reg_req, ct)?;
+
+        idms_prox_write
+            .commit()
+            .map(|()| resp)
+            .map_err(Oauth2Error::ServerError)
+    }
+
     #[instrument(
         level = "info",
         skip_all,
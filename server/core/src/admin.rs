@@ -1,18 +1,22 @@
 use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
 use crate::repl::ReplCtrl;
-use crate::CoreAction;
+use crate::{ConfigReloadRequest, CoreAction};
 use bytes::{BufMut, BytesMut};
 use futures::{SinkExt, StreamExt};
 use kanidm_lib_crypto::serialise::x509b64;
 use kanidm_utils_users::get_current_uid;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::io;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
+use tokio::sync::Mutex;
 use tokio_util::codec::{Decoder, Encoder, Framed};
 use tracing::{span, Instrument, Level};
 use uuid::Uuid;
@@ -20,18 +24,46 @@ use uuid::Uuid;
 pub use kanidm_proto::internal::{
     DomainInfo as ProtoDomainInfo, DomainUpgradeCheckReport as ProtoDomainUpgradeCheckReport,
     DomainUpgradeCheckStatus as ProtoDomainUpgradeCheckStatus,
+    KeyObjectReport as ProtoKeyObjectReport, QueryAnalysisReport as ProtoQueryAnalysisReport,
+    ReindexState as ProtoReindexState, ReindexStatus as ProtoReindexStatus,
+    ReplicationStatusReport as ProtoReplicationStatusReport, SchemaReport as ProtoSchemaReport,
+    SelfTestReport as ProtoSelfTestReport, SelfTestStatus as ProtoSelfTestStatus,
 };
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum AdminTaskRequest {
-    RecoverAccount { name: String },
+    RecoverAccount {
+        name: String,
+        tenant: Option<String>,
+    },
     ShowReplicationCertificate,
     RenewReplicationCertificate,
     RefreshReplicationConsumer,
     DomainShow,
     DomainUpgradeCheck,
     DomainRaise,
-    DomainRemigrate { level: Option<u32> },
+    DomainRemigrate {
+        level: Option<u32>,
+    },
+    SelfTest,
+    ReindexStart,
+    ReindexStatus,
+    DatabaseAnalyze,
+    SchemaShow,
+    KeyObjectList,
+    KeyObjectRotate {
+        key_object: String,
+    },
+    KeyObjectRevoke {
+        key_object: String,
+        key_id: String,
+    },
+    ReplicationStatus,
+    ReloadConfig,
+    /// Enable or disable maintenance mode. If unset, the current state is returned.
+    MaintenanceMode {
+        enabled: Option<bool>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -51,6 +83,30 @@ pub enum AdminTaskResponse {
     DomainShow {
         domain_info: ProtoDomainInfo,
     },
+    SelfTest {
+        report: ProtoSelfTestReport,
+    },
+    Reindex {
+        status: ProtoReindexStatus,
+    },
+    DatabaseAnalyze {
+        report: ProtoQueryAnalysisReport,
+    },
+    SchemaShow {
+        report: ProtoSchemaReport,
+    },
+    KeyObjectList {
+        key_objects: Vec<ProtoKeyObjectReport>,
+    },
+    ReplicationStatus {
+        report: ProtoReplicationStatusReport,
+    },
+    ReloadConfig {
+        changed: Vec<String>,
+    },
+    MaintenanceMode {
+        enabled: bool,
+    },
     Success,
     Error,
 }
@@ -132,6 +188,9 @@ impl AdminActor {
         server_ro: &'static QueryServerReadV1,
         mut broadcast_rx: broadcast::Receiver<CoreAction>,
         repl_ctrl_tx: Option<mpsc::Sender<ReplCtrl>>,
+        max_clock_skew: Duration,
+        config_reload_tx: mpsc::Sender<ConfigReloadRequest>,
+        tenants_rw: BTreeMap<String, &'static QueryServerWriteV1>,
     ) -> Result<tokio::task::JoinHandle<()>, ()> {
         debug!("🧹 Cleaning up sockets from previous invocations");
         rm_if_exist(sock_path);
@@ -148,6 +207,11 @@ impl AdminActor {
         // what is the uid we are running as?
         let cuid = get_current_uid();
 
+        // Shared by all clients, so that the progress of an online reindex can be checked from
+        // a later connection.
+        let reindex_status = Arc::new(Mutex::new(ProtoReindexStatus::default()));
+        let tenants_rw = Arc::new(tenants_rw);
+
         let handle = tokio::spawn(async move {
             loop {
                 tokio::select! {
@@ -178,8 +242,11 @@ impl AdminActor {
 
                                 // spawn the worker.
                                 let task_repl_ctrl_tx = repl_ctrl_tx.clone();
+                                let task_reindex_status = reindex_status.clone();
+                                let task_config_reload_tx = config_reload_tx.clone();
+                                let task_tenants_rw = tenants_rw.clone();
                                 tokio::spawn(async move {
-                                    if let Err(e) = handle_client(socket, server_rw, server_ro, task_repl_ctrl_tx).await {
+                                    if let Err(e) = handle_client(socket, server_rw, server_ro, task_repl_ctrl_tx, task_reindex_status, max_clock_skew, task_config_reload_tx, task_tenants_rw).await {
                                         error!(err = ?e, "admin client error");
                                     }
                                 });
@@ -291,11 +358,82 @@ async fn replication_consumer_refresh(ctrl_tx: &mut mpsc::Sender<ReplCtrl>) -> A
     }
 }
 
+async fn reload_config(config_reload_tx: &mpsc::Sender<ConfigReloadRequest>) -> AdminTaskResponse {
+    let (tx, rx) = oneshot::channel();
+
+    if config_reload_tx
+        .send(ConfigReloadRequest { respond: tx })
+        .await
+        .is_err()
+    {
+        error!("configuration reload channel has shutdown");
+        return AdminTaskResponse::Error;
+    }
+
+    match rx.await {
+        Ok(Ok(changed)) => AdminTaskResponse::ReloadConfig { changed },
+        Ok(Err(reason)) => {
+            error!(%reason, "configuration reload failed, the current configuration is unchanged");
+            AdminTaskResponse::Error
+        }
+        Err(_) => {
+            error!("configuration reload channel did not respond with reload status.");
+            AdminTaskResponse::Error
+        }
+    }
+}
+
+async fn start_online_reindex(
+    server_rw: &'static QueryServerWriteV1,
+    reindex_status: &Arc<Mutex<ProtoReindexStatus>>,
+    eventid: Uuid,
+) -> AdminTaskResponse {
+    let mut status = reindex_status.lock().await;
+
+    if status.state == ProtoReindexState::Running {
+        warn!("online reindex is already running");
+    } else {
+        *status = ProtoReindexStatus {
+            state: ProtoReindexState::Running,
+            ..Default::default()
+        };
+
+        let task_reindex_status = reindex_status.clone();
+        tokio::spawn(async move {
+            if let Err(err) = server_rw
+                .handle_online_reindex(&task_reindex_status, eventid)
+                .await
+            {
+                error!(?err, "online reindex failed");
+                task_reindex_status.lock().await.state = ProtoReindexState::Failed;
+            }
+        });
+    }
+
+    AdminTaskResponse::Reindex {
+        status: status.clone(),
+    }
+}
+
+pub(crate) fn log_self_test_report(report: &ProtoSelfTestReport) {
+    for item in report.items.iter() {
+        match item.status {
+            ProtoSelfTestStatus::Pass => info!(check = %item.check, "✅ {}", item.detail),
+            ProtoSelfTestStatus::Warn => warn!(check = %item.check, "⚠️  {}", item.detail),
+            ProtoSelfTestStatus::Fail => error!(check = %item.check, "🚨 {}", item.detail),
+        }
+    }
+}
+
 async fn handle_client(
     sock: UnixStream,
     server_rw: &'static QueryServerWriteV1,
     server_ro: &'static QueryServerReadV1,
     mut repl_ctrl_tx: Option<mpsc::Sender<ReplCtrl>>,
+    reindex_status: Arc<Mutex<ProtoReindexStatus>>,
+    max_clock_skew: Duration,
+    config_reload_tx: mpsc::Sender<ConfigReloadRequest>,
+    tenants_rw: Arc<BTreeMap<String, &'static QueryServerWriteV1>>,
 ) -> Result<(), Box<dyn Error>> {
     debug!("Accepted admin socket connection");
 
@@ -309,11 +447,27 @@ async fn handle_client(
 
         let resp = async {
             match req {
-                AdminTaskRequest::RecoverAccount { name } => {
-                    match server_rw.handle_admin_recover_account(name, eventid).await {
-                        Ok(password) => AdminTaskResponse::RecoverAccount { password },
-                        Err(e) => {
-                            error!(err = ?e, "error during recover-account");
+                AdminTaskRequest::RecoverAccount { name, tenant } => {
+                    let account_server_rw = match tenant.as_ref() {
+                        Some(tenant) => tenants_rw.get(tenant).copied(),
+                        None => Some(server_rw),
+                    };
+
+                    match account_server_rw {
+                        Some(account_server_rw) => {
+                            match account_server_rw
+                                .handle_admin_recover_account(name, eventid)
+                                .await
+                            {
+                                Ok(password) => AdminTaskResponse::RecoverAccount { password },
+                                Err(e) => {
+                                    error!(err = ?e, "error during recover-account");
+                                    AdminTaskResponse::Error
+                                }
+                            }
+                        }
+                        None => {
+                            error!(?tenant, "this server does not serve the tenant");
                             AdminTaskResponse::Error
                         }
                     }
@@ -373,6 +527,94 @@ async fn handle_client(
                         }
                     }
                 }
+                AdminTaskRequest::SelfTest => {
+                    match server_ro.handle_self_test(max_clock_skew, eventid).await {
+                        Ok(report) => {
+                            log_self_test_report(&report);
+                            AdminTaskResponse::SelfTest { report }
+                        }
+                        Err(e) => {
+                            error!(err = ?e, "error during self test");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::ReindexStart => {
+                    start_online_reindex(server_rw, &reindex_status, eventid).await
+                }
+                AdminTaskRequest::ReindexStatus => AdminTaskResponse::Reindex {
+                    status: reindex_status.lock().await.clone(),
+                },
+                AdminTaskRequest::DatabaseAnalyze => {
+                    match server_ro.handle_database_analyze(eventid).await {
+                        Ok(report) => AdminTaskResponse::DatabaseAnalyze { report },
+                        Err(e) => {
+                            error!(err = ?e, "error during database analyze");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::SchemaShow => match server_ro.handle_schema_show(eventid).await {
+                    Ok(report) => AdminTaskResponse::SchemaShow { report },
+                    Err(e) => {
+                        error!(err = ?e, "error during schema show");
+                        AdminTaskResponse::Error
+                    }
+                },
+                AdminTaskRequest::KeyObjectList => {
+                    match server_ro.handle_key_object_list(eventid).await {
+                        Ok(key_objects) => AdminTaskResponse::KeyObjectList { key_objects },
+                        Err(e) => {
+                            error!(err = ?e, "error during key object list");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::KeyObjectRotate { key_object } => {
+                    match server_rw
+                        .handle_key_object_rotate(key_object, eventid)
+                        .await
+                    {
+                        Ok(()) => AdminTaskResponse::Success,
+                        Err(e) => {
+                            error!(err = ?e, "error during key object rotate");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::KeyObjectRevoke { key_object, key_id } => {
+                    match server_rw
+                        .handle_key_object_revoke(key_object, key_id, eventid)
+                        .await
+                    {
+                        Ok(()) => AdminTaskResponse::Success,
+                        Err(e) => {
+                            error!(err = ?e, "error during key object revoke");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::ReplicationStatus => {
+                    match server_ro.handle_replication_status(eventid).await {
+                        Ok(report) => AdminTaskResponse::ReplicationStatus { report },
+                        Err(e) => {
+                            error!(err = ?e, "error during replication status");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::ReloadConfig => reload_config(&config_reload_tx).await,
+                AdminTaskRequest::MaintenanceMode { enabled } => {
+                    // Tenants are backed up and upgraded with the server, so they share its
+                    // maintenance mode.
+                    for tenant_rw in tenants_rw.values() {
+                        tenant_rw.handle_maintenance_mode(enabled, eventid);
+                    }
+
+                    AdminTaskResponse::MaintenanceMode {
+                        enabled: server_rw.handle_maintenance_mode(enabled, eventid),
+                    }
+                }
             }
         }
         .instrument(nspan)
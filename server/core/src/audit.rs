@@ -0,0 +1,638 @@
+//! The audit event store. Audit events are held in two tiers. The hot tier is a
+//! dedicated database, separate to the main database, that holds recent events so that
+//! they can be queried. Once events age out of the hot tier they are exported to the
+//! cold tier, which is a set of json lines files that can be shipped to long term storage
+//! or ingested by a log pipeline.
+//!
+//! Events can also be written to sinks as they occur, such as a log file or the local syslog
+//! daemon, for consumers that need them in real time.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+#[cfg(target_family = "unix")]
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use kanidm_proto::internal::{OperationError, WebhookDelivery};
+use kanidmd_lib::idm::audit::AuditRecord;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Deserialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::config::AuditConfig;
+
+/// The maximum number of records that may be returned from a single query of the hot tier.
+const AUDIT_QUERY_LIMIT_MAX: usize = 1024;
+
+#[cfg(target_family = "unix")]
+const SYSLOG_SOCKET_PATH: &str = "/dev/log";
+
+/// Audit events are sent to syslog with the authpriv facility at the info severity.
+#[cfg(target_family = "unix")]
+const SYSLOG_PRIORITY: u8 = (10 << 3) | 6;
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct AuditQuery {
+    /// Only return events that occurred at or after this time.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub since: Option<OffsetDateTime>,
+    /// Only return events that occurred at or before this time.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub until: Option<OffsetDateTime>,
+    /// Only return events of this type.
+    pub event: Option<String>,
+    /// Only return events that concern this entry.
+    pub target: Option<Uuid>,
+    pub limit: Option<usize>,
+}
+
+enum AuditSink {
+    File(PathBuf, File),
+    #[cfg(target_family = "unix")]
+    Syslog(UnixDatagram),
+}
+
+impl AuditSink {
+    fn write(&self, audit_event: &str) -> std::io::Result<()> {
+        match self {
+            AuditSink::File(_, file) => {
+                let mut file = file;
+                writeln!(file, "{}", audit_event)
+            }
+            #[cfg(target_family = "unix")]
+            AuditSink::Syslog(socket) => {
+                let msg = format!(
+                    "<{}>kanidmd[{}]: {}",
+                    SYSLOG_PRIORITY,
+                    std::process::id(),
+                    audit_event
+                );
+                socket.send(msg.as_bytes()).map(|_| ())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for AuditSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditSink::File(path, _) => write!(f, "file {}", path.display()),
+            #[cfg(target_family = "unix")]
+            AuditSink::Syslog(_) => write!(f, "syslog"),
+        }
+    }
+}
+
+pub struct AuditStore {
+    conn: Mutex<Connection>,
+    hot_retention: time::Duration,
+    export_path: Option<PathBuf>,
+    sinks: Vec<AuditSink>,
+}
+
+fn sqlite_error(e: rusqlite::Error) -> OperationError {
+    admin_error!(?e, "SQLite Error");
+    OperationError::SqliteError
+}
+
+fn serde_json_error(e: serde_json::Error) -> OperationError {
+    admin_error!(?e, "Serde JSON Error");
+    OperationError::SerdeJsonError
+}
+
+impl AuditStore {
+    pub fn new(config: &AuditConfig) -> Result<Self, OperationError> {
+        let conn = match &config.path {
+            Some(path) => {
+                Connection::open_with_flags(path, OpenFlags::default()).map_err(sqlite_error)?
+            }
+            None => {
+                warn!("No audit database path set, the hot tier will be held in memory only.");
+                Connection::open_in_memory().map_err(sqlite_error)?
+            }
+        };
+
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS audit_hot (
+                id TEXT PRIMARY KEY,
+                time INTEGER NOT NULL,
+                event TEXT NOT NULL,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS audit_hot_time_idx ON audit_hot (time);
+            CREATE TABLE IF NOT EXISTS session_sources (
+                uuid TEXT NOT NULL,
+                source TEXT NOT NULL,
+                time INTEGER NOT NULL,
+                PRIMARY KEY (uuid, source)
+            );
+            CREATE TABLE IF NOT EXISTS webhook_deliveries (
+                id TEXT PRIMARY KEY,
+                webhook TEXT NOT NULL,
+                time INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS webhook_deliveries_webhook_idx
+                ON webhook_deliveries (webhook, time);",
+        )
+        .map_err(sqlite_error)?;
+
+        let mut sinks = Vec::with_capacity(2);
+
+        if let Some(log_path) = &config.log_path {
+            let log_path = PathBuf::from(log_path);
+            let file = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&log_path)
+                .map_err(|e| {
+                    error!(?e, ?log_path, "Unable to open audit log file");
+                    OperationError::FsError
+                })?;
+            sinks.push(AuditSink::File(log_path, file));
+        }
+
+        if config.syslog {
+            #[cfg(target_family = "unix")]
+            {
+                let socket = UnixDatagram::unbound()
+                    .and_then(|socket| socket.connect(SYSLOG_SOCKET_PATH).map(|_| socket))
+                    .map_err(|e| {
+                        error!(?e, "Unable to connect to syslog at {}", SYSLOG_SOCKET_PATH);
+                        OperationError::FsError
+                    })?;
+                sinks.push(AuditSink::Syslog(socket));
+            }
+            #[cfg(not(target_family = "unix"))]
+            warn!("Sending audit events to syslog is not supported on this platform.");
+        }
+
+        Ok(AuditStore {
+            conn: Mutex::new(conn),
+            hot_retention: time::Duration::days(config.hot_retention_days.into()),
+            export_path: config.export_path.as_ref().map(PathBuf::from),
+            sinks,
+        })
+    }
+
+    /// Write an audit event to each of the configured sinks. A failure to write to one sink
+    /// does not prevent the event being written to the others.
+    pub fn write_sinks(&self, audit_event: &str) {
+        for sink in self.sinks.iter() {
+            if let Err(e) = sink.write(audit_event) {
+                error!(?e, "Unable to write audit event to {}", sink);
+            }
+        }
+    }
+
+    /// The oldest point in time that is still within the hot tier.
+    fn hot_floor(&self, now: OffsetDateTime) -> i64 {
+        (now - self.hot_retention).unix_timestamp()
+    }
+
+    pub fn insert(&self, record: &AuditRecord) -> Result<(), OperationError> {
+        let data = serde_json::to_string(record).map_err(serde_json_error)?;
+
+        let conn = self.conn.lock().map_err(|_| {
+            error!("Audit store lock poisoned");
+            OperationError::InvalidState
+        })?;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO audit_hot (id, time, event, data) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                record.id.to_string(),
+                record.event.time().unix_timestamp(),
+                record.event.name(),
+                data
+            ],
+        )
+        .map(|_| ())
+        .map_err(sqlite_error)
+    }
+
+    /// Record that a session was created for this account from this source. Returns true if
+    /// the account has created sessions before, but never from this source.
+    pub fn record_session_source(
+        &self,
+        uuid: Uuid,
+        source: &str,
+        time: OffsetDateTime,
+    ) -> Result<bool, OperationError> {
+        let mut conn = self.conn.lock().map_err(|_| {
+            error!("Audit store lock poisoned");
+            OperationError::InvalidState
+        })?;
+
+        let txn = conn.transaction().map_err(sqlite_error)?;
+
+        let known = txn
+            .query_row(
+                "SELECT COUNT(*) FROM session_sources WHERE uuid = ?1",
+                params![uuid.to_string()],
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(sqlite_error)?;
+
+        let inserted = txn
+            .execute(
+                "INSERT OR IGNORE INTO session_sources (uuid, source, time) VALUES (?1, ?2, ?3)",
+                params![uuid.to_string(), source, time.unix_timestamp()],
+            )
+            .map_err(sqlite_error)?;
+
+        txn.commit().map_err(sqlite_error)?;
+
+        Ok(known > 0 && inserted > 0)
+    }
+
+    /// Record the current status of the delivery of an event to a webhook, replacing any
+    /// previous status of the same delivery.
+    pub fn record_webhook_delivery(
+        &self,
+        delivery: &WebhookDelivery,
+    ) -> Result<(), OperationError> {
+        let data = serde_json::to_string(delivery).map_err(serde_json_error)?;
+
+        let conn = self.conn.lock().map_err(|_| {
+            error!("Audit store lock poisoned");
+            OperationError::InvalidState
+        })?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO webhook_deliveries (id, webhook, time, data) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                delivery.id.to_string(),
+                delivery.webhook.to_string(),
+                delivery.time.unix_timestamp(),
+                data
+            ],
+        )
+        .map(|_| ())
+        .map_err(sqlite_error)
+    }
+
+    /// The most recent deliveries to a webhook, newest first. Deliveries are kept for as long
+    /// as events are held in the hot tier.
+    pub(crate) fn webhook_deliveries(
+        &self,
+        webhook: Uuid,
+        now: OffsetDateTime,
+    ) -> Result<Vec<WebhookDelivery>, OperationError> {
+        let conn = self.conn.lock().map_err(|_| {
+            error!("Audit store lock poisoned");
+            OperationError::InvalidState
+        })?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT data FROM webhook_deliveries
+                WHERE webhook = ?1 AND time >= ?2
+                ORDER BY time DESC LIMIT ?3",
+            )
+            .map_err(sqlite_error)?;
+
+        let rows = stmt
+            .query_map(
+                params![
+                    webhook.to_string(),
+                    self.hot_floor(now),
+                    AUDIT_QUERY_LIMIT_MAX as i64
+                ],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(sqlite_error)?;
+
+        rows.map(|data| {
+            data.map_err(sqlite_error).and_then(|data| {
+                serde_json::from_str::<WebhookDelivery>(&data).map_err(serde_json_error)
+            })
+        })
+        .collect()
+    }
+
+    /// Query the hot tier. Events that have aged out of the hot tier are never returned,
+    /// even if they have not yet been exported.
+    pub(crate) fn query(
+        &self,
+        query: &AuditQuery,
+        now: OffsetDateTime,
+    ) -> Result<Vec<AuditRecord>, OperationError> {
+        let floor = self.hot_floor(now);
+        let since = query
+            .since
+            .map(|t| t.unix_timestamp().max(floor))
+            .unwrap_or(floor);
+        let until = query
+            .until
+            .map(|t| t.unix_timestamp())
+            .unwrap_or(i64::MAX);
+        let limit = query
+            .limit
+            .unwrap_or(AUDIT_QUERY_LIMIT_MAX)
+            .min(AUDIT_QUERY_LIMIT_MAX) as i64;
+
+        let conn = self.conn.lock().map_err(|_| {
+            error!("Audit store lock poisoned");
+            OperationError::InvalidState
+        })?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT data FROM audit_hot
+                WHERE time >= ?1 AND time <= ?2 AND (?3 IS NULL OR event = ?3)
+                AND (?5 IS NULL
+                    OR json_extract(data, '$.uuid') = ?5
+                    OR EXISTS (SELECT 1 FROM json_each(data, '$.targets') WHERE value = ?5))
+                ORDER BY time ASC LIMIT ?4",
+            )
+            .map_err(sqlite_error)?;
+
+        // Events refer to a single entry by uuid, or to the set of entries that an
+        // operation applied to by targets.
+        let target = query.target.map(|uuid| uuid.to_string());
+
+        let rows = stmt
+            .query_map(params![since, until, query.event, limit, target], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(sqlite_error)?;
+
+        rows.map(|data| {
+            data.map_err(sqlite_error).and_then(|data| {
+                serde_json::from_str::<AuditRecord>(&data).map_err(serde_json_error)
+            })
+        })
+        .collect()
+    }
+
+    /// Move all events that have aged out of the hot tier to the cold tier. Returns the
+    /// number of events that were removed from the hot tier.
+    pub fn export_expired(&self, now: OffsetDateTime) -> Result<usize, OperationError> {
+        let floor = self.hot_floor(now);
+
+        let mut conn = self.conn.lock().map_err(|_| {
+            error!("Audit store lock poisoned");
+            OperationError::InvalidState
+        })?;
+
+        let txn = conn.transaction().map_err(sqlite_error)?;
+
+        if let Some(export_path) = &self.export_path {
+            let mut stmt = txn
+                .prepare("SELECT data FROM audit_hot WHERE time < ?1 ORDER BY time ASC")
+                .map_err(sqlite_error)?;
+
+            let records = stmt
+                .query_map(params![floor], |row| row.get::<_, String>(0))
+                .map_err(sqlite_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(sqlite_error)?;
+
+            if !records.is_empty() {
+                let timestamp = now.format(&Rfc3339).map_err(|e| {
+                    error!(?e, "Unable to format audit export timestamp");
+                    OperationError::InvalidState
+                })?;
+                let dest_file = export_path.join(format!("audit-{}.jsonl", timestamp));
+
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&dest_file)
+                    .map_err(|e| {
+                        error!(?e, ?dest_file, "Unable to create audit export file");
+                        OperationError::FsError
+                    })?;
+
+                let mut writer = BufWriter::new(file);
+                for record in records.iter() {
+                    writeln!(writer, "{}", record).map_err(|e| {
+                        error!(?e, ?dest_file, "Unable to write audit export file");
+                        OperationError::FsError
+                    })?;
+                }
+                writer.flush().map_err(|e| {
+                    error!(?e, ?dest_file, "Unable to write audit export file");
+                    OperationError::FsError
+                })?;
+
+                info!(
+                    "Exported {} audit events to {}",
+                    records.len(),
+                    dest_file.display()
+                );
+            }
+        }
+
+        let removed = txn
+            .execute("DELETE FROM audit_hot WHERE time < ?1", params![floor])
+            .map_err(sqlite_error)?;
+
+        txn.execute(
+            "DELETE FROM webhook_deliveries WHERE time < ?1",
+            params![floor],
+        )
+        .map_err(sqlite_error)?;
+
+        txn.commit().map_err(sqlite_error)?;
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditQuery, AuditStore};
+    use crate::config::AuditConfig;
+    use kanidm_proto::internal::{WebhookAction, WebhookDelivery, WebhookDeliveryState};
+    use kanidmd_lib::idm::audit::{AuditEvent, AuditRecord, AuditSource};
+    use kanidmd_lib::prelude::*;
+    use std::collections::BTreeSet;
+    use time::OffsetDateTime;
+
+    fn auth_denied(time: OffsetDateTime) -> AuditRecord {
+        AuditRecord::from(AuditEvent::AuthenticationDenied {
+            source: AuditSource::Internal,
+            uuid: UUID_ADMIN,
+            spn: "admin@example.com".to_string(),
+            time,
+        })
+    }
+
+    #[test]
+    fn test_audit_store_hot_tier() {
+        let store = AuditStore::new(&AuditConfig::default()).expect("Unable to open store");
+
+        let now = OffsetDateTime::now_utc();
+        let old = auth_denied(now - time::Duration::days(31));
+        let recent = auth_denied(now - time::Duration::days(1));
+        let granted = AuditRecord::from(AuditEvent::Oauth2ClientCredentialsGranted {
+            uuid: Uuid::new_v4(),
+            client_id: "test_client".to_string(),
+            session_id: Uuid::new_v4(),
+            scopes: BTreeSet::default(),
+            time: now,
+        });
+
+        store.insert(&old).expect("Unable to insert record");
+        store.insert(&recent).expect("Unable to insert record");
+        store.insert(&granted).expect("Unable to insert record");
+
+        // Queries are limited to the hot tier, even if an older time is requested.
+        let query = AuditQuery {
+            since: Some(now - time::Duration::days(365)),
+            ..Default::default()
+        };
+        let records = store.query(&query, now).expect("Unable to query");
+        assert_eq!(records, vec![recent, granted]);
+
+        let query = AuditQuery {
+            event: Some("oauth2_client_credentials_granted".to_string()),
+            ..Default::default()
+        };
+        let records = store.query(&query, now).expect("Unable to query");
+        assert_eq!(records.len(), 1);
+
+        // Events can be found by the entry they concern, either as the subject of the
+        // event or as one of the targets of an operation.
+        let query = AuditQuery {
+            target: Some(UUID_ADMIN),
+            ..Default::default()
+        };
+        let records = store.query(&query, now).expect("Unable to query");
+        assert_eq!(records.len(), 1);
+
+        let target = Uuid::new_v4();
+        let modified = AuditRecord::from(AuditEvent::EntriesModified {
+            source: AuditSource::Internal,
+            actor: UUID_ADMIN,
+            targets: BTreeSet::from([target, Uuid::new_v4()]),
+            attrs: BTreeSet::from([Attribute::Description]),
+            time: now,
+        });
+        store.insert(&modified).expect("Unable to insert record");
+
+        let query = AuditQuery {
+            target: Some(target),
+            ..Default::default()
+        };
+        let records = store.query(&query, now).expect("Unable to query");
+        assert_eq!(records, vec![modified]);
+
+        // Without an export path, expired events are removed.
+        assert_eq!(store.export_expired(now).expect("Unable to export"), 1);
+        assert_eq!(store.export_expired(now).expect("Unable to export"), 0);
+    }
+
+    #[test]
+    fn test_audit_store_log_sink() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let log_path = dir.path().join("audit.log");
+
+        let config = AuditConfig {
+            log_path: log_path.to_str().map(str::to_string),
+            ..Default::default()
+        };
+        let store = AuditStore::new(&config).expect("Unable to open store");
+
+        let now = OffsetDateTime::now_utc();
+        let records = [auth_denied(now), auth_denied(now)];
+        for record in records.iter() {
+            let data = serde_json::to_string(record).expect("Unable to serialise record");
+            store.write_sinks(&data);
+        }
+        drop(store);
+
+        // Reopening the log appends to it.
+        let store = AuditStore::new(&config).expect("Unable to open store");
+        let data = serde_json::to_string(&records[0]).expect("Unable to serialise record");
+        store.write_sinks(&data);
+
+        let contents = std::fs::read_to_string(&log_path).expect("Unable to read log");
+        let logged = contents
+            .lines()
+            .map(|line| serde_json::from_str::<AuditRecord>(line).expect("Invalid record"))
+            .collect::<Vec<_>>();
+        assert_eq!(logged.len(), 3);
+        assert_eq!(logged[0], records[0]);
+        assert_eq!(logged[1], records[1]);
+        assert_eq!(logged[2], records[0]);
+    }
+
+    #[test]
+    fn test_audit_store_session_sources() {
+        let store = AuditStore::new(&AuditConfig::default()).expect("Unable to open store");
+        let now = OffsetDateTime::now_utc();
+
+        // The first source of an account is not considered new.
+        assert!(!store
+            .record_session_source(UUID_ADMIN, "192.0.2.1", now)
+            .expect("Unable to record source"));
+        assert!(!store
+            .record_session_source(UUID_ADMIN, "192.0.2.1", now)
+            .expect("Unable to record source"));
+        assert!(store
+            .record_session_source(UUID_ADMIN, "192.0.2.2", now)
+            .expect("Unable to record source"));
+        assert!(!store
+            .record_session_source(UUID_ADMIN, "192.0.2.2", now)
+            .expect("Unable to record source"));
+
+        // Sources are tracked per account.
+        assert!(!store
+            .record_session_source(UUID_IDM_ADMIN, "192.0.2.2", now)
+            .expect("Unable to record source"));
+    }
+
+    #[test]
+    fn test_audit_store_webhook_deliveries() {
+        let store = AuditStore::new(&AuditConfig::default()).expect("Unable to open store");
+        let now = OffsetDateTime::now_utc();
+        let webhook = Uuid::new_v4();
+
+        let mut delivery = WebhookDelivery {
+            id: Uuid::new_v4(),
+            webhook,
+            action: WebhookAction::Created,
+            target: UUID_ADMIN,
+            time: now - time::Duration::days(1),
+            attempts: 1,
+            state: WebhookDeliveryState::Pending,
+            last_error: Some("connection refused".to_string()),
+        };
+        store
+            .record_webhook_delivery(&delivery)
+            .expect("Unable to record delivery");
+
+        // Later attempts replace the status of the delivery.
+        delivery.attempts = 2;
+        delivery.state = WebhookDeliveryState::Delivered;
+        delivery.last_error = None;
+        store
+            .record_webhook_delivery(&delivery)
+            .expect("Unable to record delivery");
+
+        let expired = WebhookDelivery {
+            id: Uuid::new_v4(),
+            time: now - time::Duration::days(31),
+            ..delivery.clone()
+        };
+        store
+            .record_webhook_delivery(&expired)
+            .expect("Unable to record delivery");
+
+        let deliveries = store
+            .webhook_deliveries(webhook, now)
+            .expect("Unable to query deliveries");
+        assert_eq!(deliveries, vec![delivery]);
+
+        assert!(store
+            .webhook_deliveries(Uuid::new_v4(), now)
+            .expect("Unable to query deliveries")
+            .is_empty());
+    }
+}
@@ -6,7 +6,10 @@ use openssl::error::ErrorStack;
 use openssl::nid::Nid;
 use openssl::pkey::{PKeyRef, Private};
 use openssl::rsa::Rsa;
-use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslSessionCacheMode, SslVerifyMode};
+use openssl::ssl::{
+    NameType, SniError, SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod,
+    SslSessionCacheMode, SslVerifyMode,
+};
 use openssl::x509::{
     extension::{
         AuthorityKeyIdentifier, BasicConstraints, ExtendedKeyUsage, KeyUsage,
@@ -19,6 +22,7 @@ use sketching::*;
 
 use crate::config::TlsConfiguration;
 
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::{ErrorKind, Read, Write};
 use std::path::Path;
@@ -95,11 +99,58 @@ pub fn check_privkey_minimums(privkey: &PKeyRef<Private>) -> Result<(), String>
 /// to build our sockets for HTTPS/LDAPS.
 pub fn setup_tls(
     tls_config: &Option<TlsConfiguration>,
+) -> Result<Option<SslAcceptor>, std::io::Error> {
+    setup_tls_with_tenants(tls_config, &[])
+}
+
+/// As [setup_tls], but clients that request the host of a tenant by SNI are presented the
+/// certificate of that tenant. Clients that request any other name are presented the
+/// certificate of the server.
+pub fn setup_tls_with_tenants(
+    tls_config: &Option<TlsConfiguration>,
+    tenants: &[(String, TlsConfiguration)],
 ) -> Result<Option<SslAcceptor>, std::io::Error> {
     let Some(tls_param) = tls_config.as_ref() else {
         return Ok(None);
     };
 
+    let mut tls_builder = tls_acceptor_builder(tls_param)?;
+
+    if !tenants.is_empty() {
+        let tenant_contexts = tenants
+            .iter()
+            .map(|(host, tenant_tls_param)| {
+                let tenant_acceptor = tls_acceptor_builder(tenant_tls_param)?.build();
+                check_acceptor_privkey(&tenant_acceptor)?;
+                Ok((host.to_lowercase(), tenant_acceptor.into_context()))
+            })
+            .collect::<Result<BTreeMap<_, _>, std::io::Error>>()?;
+
+        tls_builder.set_servername_callback(move |ssl, _alert| {
+            let tenant_context = ssl
+                .servername(NameType::HOST_NAME)
+                .map(|name| name.to_lowercase())
+                .and_then(|name| tenant_contexts.get(&name));
+
+            if let Some(tenant_context) = tenant_context {
+                ssl.set_ssl_context(tenant_context).map_err(|err| {
+                    error!(?err, "Failed to select the TLS certificate of tenant");
+                    SniError::ALERT_FATAL
+                })?;
+            }
+            Ok(())
+        });
+    }
+
+    let tls_acceptor = tls_builder.build();
+    check_acceptor_privkey(&tls_acceptor)?;
+
+    Ok(Some(tls_acceptor))
+}
+
+fn tls_acceptor_builder(
+    tls_param: &TlsConfiguration,
+) -> Result<SslAcceptorBuilder, std::io::Error> {
     let mut tls_builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())?;
 
     tls_builder
@@ -232,8 +283,10 @@ pub fn setup_tls(
         // End tls_client setup
     }
 
-    let tls_acceptor = tls_builder.build();
+    Ok(tls_builder)
+}
 
+fn check_acceptor_privkey(tls_acceptor: &SslAcceptor) -> Result<(), std::io::Error> {
     // let's enforce some TLS minimums!
     let privkey = tls_acceptor.context().private_key().ok_or_else(|| {
         std::io::Error::new(
@@ -247,9 +300,63 @@ pub fn setup_tls(
             ErrorKind::Other,
             format!("Private key minimums were not met: {:?}", err),
         )
+    })
+}
+
+/// Check that the TLS material of the configuration can be loaded, and that the server
+/// certificate is currently valid.
+pub fn check_tls_material(tls_config: &TlsConfiguration) -> Result<(), String> {
+    setup_tls(&Some(tls_config.clone())).map_err(|err| err.to_string())?;
+
+    let chain_pem = fs::read(&tls_config.chain).map_err(|err| {
+        format!(
+            "Unable to read TLS chain {} - {:?}",
+            tls_config.chain.display(),
+            err
+        )
     })?;
 
-    Ok(Some(tls_acceptor))
+    let chain = X509::stack_from_pem(&chain_pem).map_err(|err| {
+        format!(
+            "Unable to parse TLS chain {} - {:?}",
+            tls_config.chain.display(),
+            err
+        )
+    })?;
+
+    let server_cert = chain.first().ok_or_else(|| {
+        format!(
+            "TLS chain {} does not contain any certificates",
+            tls_config.chain.display()
+        )
+    })?;
+
+    let now = asn1::Asn1Time::days_from_now(0).map_err(|err| format!("{:?}", err))?;
+
+    if server_cert.not_before() > now {
+        return Err(format!(
+            "TLS certificate is not valid until {}",
+            server_cert.not_before()
+        ));
+    }
+
+    if server_cert.not_after() < now {
+        return Err(format!(
+            "TLS certificate expired at {}",
+            server_cert.not_after()
+        ));
+    }
+
+    Ok(())
+}
+
+/// The time that the server certificate of this acceptor expires, in seconds since the
+/// unix epoch.
+pub(crate) fn tls_acceptor_not_after(tls_acceptor: &SslAcceptor) -> Option<u64> {
+    let server_cert = tls_acceptor.context().certificate()?;
+    let epoch = asn1::Asn1Time::from_unix(0).ok()?;
+    let diff = epoch.diff(server_cert.not_after()).ok()?;
+    u64::try_from(i64::from(diff.days) * 86400 + i64::from(diff.secs)).ok()
 }
 
 fn get_ec_group() -> Result<EcGroup, ErrorStack> {
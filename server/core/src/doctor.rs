@@ -0,0 +1,561 @@
+//! The environment checks of `kanidmd doctor`. These don't need the server to be running, and
+//! inspect the files, certificates, clock, database and network that the server depends on so
+//! that problems are found before they cause an outage.
+
+use std::fs;
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use kanidm_proto::internal::{SelfTestCheck, SelfTestItem, SelfTestReport, SelfTestStatus};
+use openssl::asn1::Asn1Time;
+use openssl::x509::{X509Ref, X509VerifyResult, X509};
+use rusqlite::{Connection, OpenFlags};
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::timeout;
+use url::{Host, Url};
+
+use crate::config::Configuration;
+use crate::crypto::check_tls_material;
+use crate::repl::config::RepNodeConfig;
+
+const TLS_CERT_EXPIRY_WARNING_DAYS: i32 = 30;
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+#[cfg(target_os = "linux")]
+const CLOCK_MAX_ERROR_USEC: libc::c_long = 500_000;
+
+/// Run all of the checks, returning the findings with the most severe first.
+pub(crate) async fn run_checks(config: &Configuration, config_files: &[PathBuf]) -> SelfTestReport {
+    let mut items = file_permission_checks(config, config_files);
+
+    let chain = tls_checks(config, &mut items);
+    origin_checks(config, chain.as_deref(), &mut items).await;
+    items.push(clock_sync_check());
+    items.push(database_check(config));
+    replication_checks(config, &mut items).await;
+
+    // This is a stable sort, so findings of the same severity stay in the order they were checked.
+    items.sort_by(|a, b| b.status.cmp(&a.status));
+
+    SelfTestReport { items }
+}
+
+fn finding(check: SelfTestCheck, status: SelfTestStatus, detail: String) -> SelfTestItem {
+    SelfTestItem {
+        check,
+        status,
+        detail,
+    }
+}
+
+struct FilePolicy {
+    kind: &'static str,
+    /// The file must not be writable by the server.
+    readonly: bool,
+    /// The file must not be readable by everyone.
+    private: bool,
+    /// The file is created by the server if it doesn't exist.
+    created: bool,
+}
+
+const CONFIG_FILE: FilePolicy = FilePolicy {
+    kind: "configuration file",
+    readonly: true,
+    private: true,
+    created: false,
+};
+
+const TLS_CHAIN_FILE: FilePolicy = FilePolicy {
+    kind: "TLS chain",
+    readonly: true,
+    private: false,
+    created: false,
+};
+
+const TLS_KEY_FILE: FilePolicy = FilePolicy {
+    kind: "TLS key",
+    readonly: true,
+    private: true,
+    created: false,
+};
+
+const DATABASE_FILE: FilePolicy = FilePolicy {
+    kind: "database",
+    readonly: false,
+    private: true,
+    created: true,
+};
+
+fn file_permission_checks(config: &Configuration, config_files: &[PathBuf]) -> Vec<SelfTestItem> {
+    let mut files: Vec<(&FilePolicy, &Path)> = config_files
+        .iter()
+        .map(|path| (&CONFIG_FILE, path.as_path()))
+        .collect();
+
+    if let Some(tls_config) = &config.tls_config {
+        files.push((&TLS_CHAIN_FILE, tls_config.chain.as_path()));
+        files.push((&TLS_KEY_FILE, tls_config.key.as_path()));
+    }
+
+    if !config.db_path.is_empty() {
+        files.push((&DATABASE_FILE, Path::new(&config.db_path)));
+    }
+
+    files
+        .into_iter()
+        .map(|(policy, path)| file_permission_check(policy, path))
+        .collect()
+}
+
+fn file_permission_check(policy: &FilePolicy, path: &Path) -> SelfTestItem {
+    let check = SelfTestCheck::FilePermissions;
+
+    let meta = match fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(_) if policy.created && path.parent().is_some_and(Path::is_dir) => {
+            return finding(
+                check,
+                SelfTestStatus::Pass,
+                format!(
+                    "{} {} will be created when the server starts",
+                    policy.kind,
+                    path.display()
+                ),
+            );
+        }
+        Err(err) => {
+            let diag = kanidm_lib_file_permissions::diagnose_path(path);
+            return finding(
+                check,
+                SelfTestStatus::Fail,
+                format!(
+                    "unable to read {} {} - {:?}\n{}",
+                    policy.kind,
+                    path.display(),
+                    err.kind(),
+                    diag
+                ),
+            );
+        }
+    };
+
+    #[cfg(target_family = "unix")]
+    if policy.private && meta.mode() & 0o007 != 0 {
+        return finding(
+            check,
+            SelfTestStatus::Fail,
+            format!(
+                "{} {} has 'everyone' permission bits in its mode",
+                policy.kind,
+                path.display()
+            ),
+        );
+    }
+
+    if policy.readonly && !kanidm_lib_file_permissions::readonly(&meta) {
+        return finding(
+            check,
+            SelfTestStatus::Warn,
+            format!(
+                "{} {} should be readonly to the server user",
+                policy.kind,
+                path.display()
+            ),
+        );
+    }
+
+    finding(
+        check,
+        SelfTestStatus::Pass,
+        format!("{} {}", policy.kind, path.display()),
+    )
+}
+
+/// Check the TLS material, returning the chain if it could be loaded.
+fn tls_checks(config: &Configuration, items: &mut Vec<SelfTestItem>) -> Option<Vec<X509>> {
+    let check = SelfTestCheck::TlsCertificate;
+
+    let Some(tls_config) = &config.tls_config else {
+        items.push(finding(
+            check,
+            SelfTestStatus::Fail,
+            "TLS chain and key must be configured".to_string(),
+        ));
+        return None;
+    };
+
+    // This checks that the key matches the server certificate, and that it's currently valid.
+    if let Err(err) = check_tls_material(tls_config) {
+        items.push(finding(check, SelfTestStatus::Fail, err));
+        return None;
+    }
+
+    let chain = fs::read(&tls_config.chain)
+        .ok()
+        .and_then(|pem| X509::stack_from_pem(&pem).ok())?;
+
+    items.push(tls_chain_check(&chain));
+    items.push(tls_expiry_check(&chain));
+
+    Some(chain)
+}
+
+fn tls_chain_check(chain: &[X509]) -> SelfTestItem {
+    let check = SelfTestCheck::TlsCertificate;
+
+    // Each certificate must be issued by the one that follows it.
+    for (idx, pair) in chain.windows(2).enumerate() {
+        let signed = pair[1]
+            .public_key()
+            .and_then(|issuer_key| pair[0].verify(&issuer_key))
+            .unwrap_or(false);
+
+        if pair[1].issued(&pair[0]) != X509VerifyResult::OK || !signed {
+            return finding(
+                check,
+                SelfTestStatus::Fail,
+                format!(
+                    "certificate {} of the chain was not issued by the certificate that follows it",
+                    idx
+                ),
+            );
+        }
+    }
+
+    match chain {
+        [server_cert] if server_cert.issued(server_cert) == X509VerifyResult::OK => finding(
+            check,
+            SelfTestStatus::Warn,
+            "the server certificate is self signed, clients must be configured to trust it"
+                .to_string(),
+        ),
+        [_] => finding(
+            check,
+            SelfTestStatus::Warn,
+            "the chain only contains the server certificate, clients may be unable to verify it without the intermediate certificates"
+                .to_string(),
+        ),
+        _ => finding(
+            check,
+            SelfTestStatus::Pass,
+            format!("chain of {} certificates is in order", chain.len()),
+        ),
+    }
+}
+
+fn tls_expiry_check(chain: &[X509]) -> SelfTestItem {
+    let check = SelfTestCheck::TlsCertificate;
+
+    // The chain is only as valid as the certificate in it that expires first.
+    let remaining_days = Asn1Time::days_from_now(0).ok().and_then(|now| {
+        chain
+            .iter()
+            .filter_map(|cert| now.diff(cert.not_after()).ok())
+            .map(|diff| {
+                if diff.secs < 0 {
+                    diff.days - 1
+                } else {
+                    diff.days
+                }
+            })
+            .min()
+    });
+
+    match remaining_days {
+        Some(days) if days < 0 => finding(
+            check,
+            SelfTestStatus::Fail,
+            "a certificate of the chain has expired".to_string(),
+        ),
+        Some(days) if days < TLS_CERT_EXPIRY_WARNING_DAYS => finding(
+            check,
+            SelfTestStatus::Warn,
+            format!("the chain expires in {} days", days),
+        ),
+        Some(days) => finding(
+            check,
+            SelfTestStatus::Pass,
+            format!("the chain expires in {} days", days),
+        ),
+        None => finding(
+            check,
+            SelfTestStatus::Warn,
+            "unable to determine when the chain expires".to_string(),
+        ),
+    }
+}
+
+async fn origin_checks(
+    config: &Configuration,
+    chain: Option<&[X509]>,
+    items: &mut Vec<SelfTestItem>,
+) {
+    let origin = match Url::parse(&config.origin) {
+        Ok(origin) => origin,
+        Err(err) => {
+            items.push(finding(
+                SelfTestCheck::TlsName,
+                SelfTestStatus::Fail,
+                format!("unable to parse origin {} - {:?}", config.origin, err),
+            ));
+            return;
+        }
+    };
+
+    let (Some(host), Some(host_str)) = (origin.host(), origin.host_str()) else {
+        items.push(finding(
+            SelfTestCheck::TlsName,
+            SelfTestStatus::Fail,
+            format!("origin {} does not contain a host", origin),
+        ));
+        return;
+    };
+
+    if let Some(server_cert) = chain.and_then(|chain| chain.first()) {
+        items.push(tls_name_check(server_cert, &host));
+    }
+
+    let port = origin.port_or_known_default().unwrap_or(443);
+    items.push(dns_check(host_str, port).await);
+}
+
+/// Check that clients connecting to the origin will accept the server certificate.
+fn tls_name_check(server_cert: &X509Ref, host: &Host<&str>) -> SelfTestItem {
+    let check = SelfTestCheck::TlsName;
+
+    let Some(alt_names) = server_cert.subject_alt_names() else {
+        return finding(
+            check,
+            SelfTestStatus::Fail,
+            "the server certificate has no subject alternative names, clients will not accept it"
+                .to_string(),
+        );
+    };
+
+    let matched = alt_names.iter().any(|alt_name| match host {
+        Host::Domain(domain) => alt_name
+            .dnsname()
+            .is_some_and(|dnsname| dns_name_matches(dnsname, domain)),
+        Host::Ipv4(addr) => alt_name.ipaddress() == Some(&addr.octets()[..]),
+        Host::Ipv6(addr) => alt_name.ipaddress() == Some(&addr.octets()[..]),
+    });
+
+    if matched {
+        finding(
+            check,
+            SelfTestStatus::Pass,
+            format!("the server certificate is valid for {}", host),
+        )
+    } else {
+        let names: Vec<&str> = alt_names
+            .iter()
+            .filter_map(|alt_name| alt_name.dnsname())
+            .collect();
+        finding(
+            check,
+            SelfTestStatus::Fail,
+            format!(
+                "the server certificate is not valid for {}, it is valid for {}",
+                host,
+                names.join(", ")
+            ),
+        )
+    }
+}
+
+/// Match a host against a dns name of a certificate, where a wildcard only matches a
+/// single label.
+fn dns_name_matches(dnsname: &str, host: &str) -> bool {
+    match dnsname.strip_prefix("*.") {
+        Some(suffix) => host
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest.eq_ignore_ascii_case(suffix)),
+        None => dnsname.eq_ignore_ascii_case(host),
+    }
+}
+
+async fn dns_check(host: &str, port: u16) -> SelfTestItem {
+    let check = SelfTestCheck::Dns;
+
+    match timeout(NETWORK_TIMEOUT, lookup_host((host, port))).await {
+        Ok(Ok(addrs)) => {
+            let addrs: Vec<String> = addrs.map(|addr| addr.ip().to_string()).collect();
+            finding(
+                check,
+                SelfTestStatus::Pass,
+                format!("{} resolves to {}", host, addrs.join(", ")),
+            )
+        }
+        // This host may be using different name servers to clients, so this is only a warning.
+        Ok(Err(err)) => finding(
+            check,
+            SelfTestStatus::Warn,
+            format!("unable to resolve {} - {}", host, err),
+        ),
+        Err(_) => finding(
+            check,
+            SelfTestStatus::Warn,
+            format!("timed out resolving {}", host),
+        ),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn clock_sync_check() -> SelfTestItem {
+    let check = SelfTestCheck::ClockSync;
+
+    // With no modes set, this only reads the state of the kernel clock.
+    let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+
+    match state {
+        -1 => finding(
+            check,
+            SelfTestStatus::Warn,
+            format!(
+                "unable to read the state of the system clock - {}",
+                std::io::Error::last_os_error()
+            ),
+        ),
+        libc::TIME_ERROR => finding(
+            check,
+            SelfTestStatus::Fail,
+            "the system clock is not synchronised, sessions and replication depend on an accurate clock so ntp should be configured"
+                .to_string(),
+        ),
+        _ if timex.maxerror > CLOCK_MAX_ERROR_USEC => finding(
+            check,
+            SelfTestStatus::Warn,
+            format!(
+                "the system clock may be off by up to {}ms",
+                timex.maxerror / 1000
+            ),
+        ),
+        _ => finding(
+            check,
+            SelfTestStatus::Pass,
+            format!(
+                "the system clock is synchronised to within {}ms",
+                timex.maxerror / 1000
+            ),
+        ),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clock_sync_check() -> SelfTestItem {
+    finding(
+        SelfTestCheck::ClockSync,
+        SelfTestStatus::Warn,
+        "unable to check the synchronisation of the system clock on this platform".to_string(),
+    )
+}
+
+fn database_check(config: &Configuration) -> SelfTestItem {
+    let check = SelfTestCheck::Database;
+
+    let path = Path::new(&config.db_path);
+    if config.db_path.is_empty() {
+        return finding(
+            check,
+            SelfTestStatus::Fail,
+            "db_path must be configured".to_string(),
+        );
+    } else if !path.exists() {
+        return finding(
+            check,
+            SelfTestStatus::Pass,
+            format!("{} will be created when the server starts", path.display()),
+        );
+    }
+
+    // Only read the database so that this can run alongside the server.
+    let result = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .and_then(|conn| conn.query_row("PRAGMA quick_check", [], |row| row.get::<_, String>(0)));
+
+    match result {
+        Ok(result) if result == "ok" => finding(
+            check,
+            SelfTestStatus::Pass,
+            format!("quick check of {} passed", path.display()),
+        ),
+        Ok(result) => finding(
+            check,
+            SelfTestStatus::Fail,
+            format!(
+                "quick check of {} failed, the database should be restored from a backup - {}",
+                path.display(),
+                result
+            ),
+        ),
+        Err(err) => finding(
+            check,
+            SelfTestStatus::Fail,
+            format!("unable to check {} - {:?}", path.display(), err),
+        ),
+    }
+}
+
+async fn replication_checks(config: &Configuration, items: &mut Vec<SelfTestItem>) {
+    let Some(repl_config) = &config.repl_config else {
+        return;
+    };
+
+    for (url, node) in repl_config.manual.iter() {
+        // Consumers connect to us, so there is nothing to reach.
+        if matches!(node, RepNodeConfig::AllowPull { .. }) {
+            continue;
+        }
+        items.push(replication_check(url).await);
+    }
+}
+
+async fn replication_check(url: &Url) -> SelfTestItem {
+    let check = SelfTestCheck::Replication;
+
+    let Some(host) = url.host_str() else {
+        return finding(
+            check,
+            SelfTestStatus::Fail,
+            format!("replication partner {} does not contain a host", url),
+        );
+    };
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    match timeout(NETWORK_TIMEOUT, TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => finding(
+            check,
+            SelfTestStatus::Pass,
+            format!("able to connect to {}", url),
+        ),
+        Ok(Err(err)) => finding(
+            check,
+            SelfTestStatus::Fail,
+            format!("unable to connect to {} - {}", url, err),
+        ),
+        Err(_) => finding(
+            check,
+            SelfTestStatus::Fail,
+            format!("timed out connecting to {}", url),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dns_name_matches;
+
+    #[test]
+    fn test_doctor_dns_name_matches() {
+        assert!(dns_name_matches("idm.example.com", "idm.example.com"));
+        assert!(dns_name_matches("IDM.example.com", "idm.example.com"));
+        assert!(dns_name_matches("*.example.com", "idm.example.com"));
+        assert!(!dns_name_matches("*.example.com", "example.com"));
+        assert!(!dns_name_matches("*.example.com", "a.idm.example.com"));
+        assert!(!dns_name_matches("idm.example.com", "example.com"));
+    }
+}
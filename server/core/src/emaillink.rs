@@ -0,0 +1,49 @@
+//! Delivery of email links by mail. The idm server produces a message when an authentication
+//! session selects the email link mechanism, and this sends it to the account. This only runs
+//! when smtp is configured, otherwise email links are never offered.
+
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use kanidmd_lib::idm::emaillink::EmailLinkMessage;
+use kanidmd_lib::idm::server::IdmServer;
+
+use crate::mail::Mailer;
+use crate::CoreAction;
+
+pub(crate) struct EmailLinkActor;
+
+impl EmailLinkActor {
+    pub fn start(
+        idms: Arc<IdmServer>,
+        mailer: Arc<Mailer>,
+        mut email_link_rx: UnboundedReceiver<EmailLinkMessage>,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                        }
+                    }
+                    message = email_link_rx.recv() => {
+                        let Some(message) = message else {
+                            // Channel has closed, stop the task.
+                            break
+                        };
+                        let branding = mailer.branding(&idms.domain_read());
+                        if let Err(err) = mailer.send_email_link(&branding, &message).await {
+                            error!(?err, spn = %message.spn, "Unable to deliver email link");
+                        }
+                    }
+                }
+            }
+
+            info!("Stopped {}", super::TaskName::EmailLinkActor);
+        })
+    }
+}
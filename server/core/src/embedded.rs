@@ -0,0 +1,126 @@
+//! Support for running the server inside of another application. Rather than running
+//! kanidmd as a separate daemon, a host application can build a [Configuration] and start
+//! the server core within its own async runtime:
+//!
+//! ```ignore
+//! let mut config = Configuration::new();
+//! config.update_db_path("/var/lib/appliance/kanidm.db");
+//! config.update_domain("idm.appliance.example.com");
+//! config.update_origin("https://idm.appliance.example.com");
+//! config.update_tls(&Some(chain), &Some(key), &None);
+//! config.update_admin_bind_path(&Some("/var/lib/appliance/kanidmd.sock".to_string()));
+//!
+//! let mut core_handle = create_server_core(config, false).await?;
+//!
+//! let client = core_handle.client().set_bearer_token(api_token);
+//! let whoami = client.whoami().await?;
+//!
+//! core_handle.shutdown().await;
+//! ```
+//!
+//! The [CoreHandle] provides the [QueryServer] and [IdmServer] for operations that need
+//! direct access to the database, as well as an [EmbeddedClient]. The [EmbeddedClient]
+//! performs operations through the same actors as the https server without a network
+//! round trip, so the same authentication and access controls apply.
+//!
+//! [Configuration]: crate::config::Configuration
+//! [CoreHandle]: crate::CoreHandle
+//! [QueryServer]: kanidmd_lib::server::QueryServer
+//! [IdmServer]: kanidmd_lib::idm::server::IdmServer
+
+use compact_jwt::JwsCompact;
+use kanidm_proto::internal::{
+    CreateRequest, DeleteRequest, Filter as ProtoFilter, ModifyList as ProtoModifyList,
+    ModifyRequest, SearchRequest,
+};
+use kanidm_proto::v1::{Entry as ProtoEntry, WhoamiResponse};
+use kanidmd_lib::prelude::*;
+
+use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
+
+/// An in-process client of an embedded server.
+#[derive(Clone)]
+pub struct EmbeddedClient {
+    qe_r_ref: &'static QueryServerReadV1,
+    qe_w_ref: &'static QueryServerWriteV1,
+    client_auth_info: ClientAuthInfo,
+}
+
+impl EmbeddedClient {
+    pub(crate) fn new(
+        qe_r_ref: &'static QueryServerReadV1,
+        qe_w_ref: &'static QueryServerWriteV1,
+    ) -> Self {
+        EmbeddedClient {
+            qe_r_ref,
+            qe_w_ref,
+            client_auth_info: ClientAuthInfo {
+                source: Source::Internal,
+                client_cert: None,
+                bearer_token: None,
+                basic_authz: None,
+                dpop: None,
+                session_proof: None,
+                user_agent: None,
+            },
+        }
+    }
+
+    /// Authenticate the operations of this client with a session or api token. Without
+    /// a token, operations are performed as anonymous.
+    pub fn set_bearer_token(mut self, token: JwsCompact) -> Self {
+        self.client_auth_info.bearer_token = Some(token);
+        self
+    }
+
+    pub async fn whoami(&self) -> Result<WhoamiResponse, OperationError> {
+        self.qe_r_ref
+            .handle_whoami(self.client_auth_info.clone(), Uuid::new_v4())
+            .await
+    }
+
+    pub async fn search(&self, filter: ProtoFilter) -> Result<Vec<ProtoEntry>, OperationError> {
+        self.qe_r_ref
+            .handle_search(
+                self.client_auth_info.clone(),
+                SearchRequest::new(filter),
+                Uuid::new_v4(),
+            )
+            .await
+            .map(|sr| sr.entries)
+    }
+
+    pub async fn create(&self, entries: Vec<ProtoEntry>) -> Result<(), OperationError> {
+        self.qe_w_ref
+            .handle_create(
+                self.client_auth_info.clone(),
+                CreateRequest::new(entries),
+                Uuid::new_v4(),
+            )
+            .await
+    }
+
+    pub async fn modify(
+        &self,
+        filter: ProtoFilter,
+        modlist: ProtoModifyList,
+    ) -> Result<(), OperationError> {
+        self.qe_w_ref
+            .handle_modify(
+                self.client_auth_info.clone(),
+                ModifyRequest::new(filter, modlist),
+                Uuid::new_v4(),
+            )
+            .await
+    }
+
+    pub async fn delete(&self, filter: ProtoFilter) -> Result<(), OperationError> {
+        self.qe_w_ref
+            .handle_delete(
+                self.client_auth_info.clone(),
+                DeleteRequest::new(filter),
+                Uuid::new_v4(),
+            )
+            .await
+    }
+}
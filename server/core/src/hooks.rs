@@ -0,0 +1,192 @@
+//! Running the hooks configured on this server after entries are changed. Each hook has its own
+//! queue, so that it's run for changes in the order they were made, and so that a slow hook
+//! doesn't delay any other. A hook that fails or times out is logged, and never retried.
+
+use std::collections::BTreeSet;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use kanidm_proto::attribute::Attribute;
+use kanidmd_lib::idm::audit::AuditEvent;
+use kanidmd_lib::idm::hook::HookPayload;
+use kanidmd_lib::idm::server::IdmServer;
+use kanidmd_lib::idm::webhook::WebhookFilter;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinSet;
+use tokio::time::{timeout, Duration};
+
+use crate::config::HookConfig;
+use crate::CoreAction;
+
+pub(crate) const HOOK_ACTION_ENV: &str = "KANIDM_HOOK_ACTION";
+pub(crate) const HOOK_TARGET_ENV: &str = "KANIDM_HOOK_TARGET";
+
+struct Hook {
+    name: String,
+    command: String,
+    args: Vec<String>,
+    filters: Vec<WebhookFilter>,
+    attrs: BTreeSet<Attribute>,
+    timeout: Duration,
+}
+
+impl From<&HookConfig> for Hook {
+    fn from(config: &HookConfig) -> Self {
+        Hook {
+            name: config.name.clone(),
+            command: config.command.clone(),
+            args: config.args.clone(),
+            // The filters were checked when the configuration was validated.
+            filters: config
+                .filters
+                .iter()
+                .filter_map(|filter| WebhookFilter::from_str(filter).ok())
+                .collect(),
+            attrs: config
+                .attrs
+                .iter()
+                .map(|attr| Attribute::from(attr.as_str()))
+                .collect(),
+            timeout: config.timeout(),
+        }
+    }
+}
+
+pub(crate) struct HookActor;
+
+impl HookActor {
+    pub fn start(
+        idms: Arc<IdmServer>,
+        hooks: &[HookConfig],
+        mut hook_rx: UnboundedReceiver<AuditEvent>,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> tokio::task::JoinHandle<()> {
+        let hooks: Vec<Arc<Hook>> = hooks.iter().map(|hook| Arc::new(hook.into())).collect();
+
+        tokio::spawn(async move {
+            // Hooks that are still running are killed at shutdown.
+            let mut workers = JoinSet::new();
+
+            let queues: Vec<(Arc<Hook>, UnboundedSender<HookPayload>)> = hooks
+                .into_iter()
+                .map(|hook| {
+                    let (queue_tx, queue_rx) = mpsc::unbounded_channel();
+                    workers.spawn(worker(hook.clone(), queue_rx));
+                    (hook, queue_tx)
+                })
+                .collect();
+
+            loop {
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                        }
+                    }
+                    event = hook_rx.recv() => {
+                        let Some(event) = event else {
+                            // Channel has closed, stop the task.
+                            break
+                        };
+
+                        let mut idms_prox_read = match idms.proxy_read().await {
+                            Ok(txn) => txn,
+                            Err(err) => {
+                                error!(?err, "Unable to determine hooks for event");
+                                continue;
+                            }
+                        };
+
+                        for (hook, queue_tx) in queues.iter() {
+                            match idms_prox_read.hook_payloads(&event, &hook.filters, &hook.attrs) {
+                                Ok(payloads) => {
+                                    for payload in payloads {
+                                        if queue_tx.send(payload).is_err() {
+                                            error!(hook = %hook.name, "Unable to queue hook event");
+                                        }
+                                    }
+                                }
+                                Err(err) => {
+                                    error!(?err, hook = %hook.name, "Unable to determine hook events");
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            info!("Stopped {}", super::TaskName::HookActor);
+        })
+    }
+}
+
+async fn worker(hook: Arc<Hook>, mut queue_rx: UnboundedReceiver<HookPayload>) {
+    while let Some(payload) = queue_rx.recv().await {
+        run(&hook, payload).await;
+    }
+}
+
+async fn run(hook: &Hook, payload: HookPayload) {
+    let body = match serde_json::to_vec(&payload) {
+        Ok(body) => body,
+        Err(err) => {
+            error!(?err, hook = %hook.name, "Unable to serialise hook event");
+            return;
+        }
+    };
+
+    let mut child = match Command::new(&hook.command)
+        .args(&hook.args)
+        .env(HOOK_ACTION_ENV, payload.event.action.to_string())
+        .env(HOOK_TARGET_ENV, payload.event.target.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            error!(?err, hook = %hook.name, "Unable to run hook");
+            return;
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pipe is closed when stdin is dropped, so the hook sees the end of the event.
+        if let Err(err) = stdin.write_all(&body).await {
+            warn!(?err, hook = %hook.name, "Unable to write event to hook");
+        }
+    }
+
+    // If the hook times out, the child is dropped with the future, which kills it.
+    match timeout(hook.timeout, child.wait_with_output()).await {
+        Ok(Ok(output)) if output.status.success() => {
+            debug!(hook = %hook.name, event = ?payload.event.id, "Hook succeeded");
+        }
+        Ok(Ok(output)) => {
+            warn!(
+                hook = %hook.name,
+                event = ?payload.event.id,
+                status = %output.status,
+                stderr = %String::from_utf8_lossy(&output.stderr),
+                "Hook failed"
+            );
+        }
+        Ok(Err(err)) => {
+            error!(?err, hook = %hook.name, event = ?payload.event.id, "Unable to wait for hook");
+        }
+        Err(_) => {
+            warn!(
+                hook = %hook.name,
+                event = ?payload.event.id,
+                timeout = ?hook.timeout,
+                "Hook timed out and was killed"
+            );
+        }
+    }
+}
@@ -46,6 +46,9 @@ impl Modify for SecurityAddon {
 
     paths(
         super::generic::status,
+        super::generic::status_live,
+        super::generic::status_ready,
+        super::generic::metrics,
         super::generic::robots_txt,
 
         super::oauth2::oauth2_image_get,
@@ -54,18 +57,22 @@ impl Modify for SecurityAddon {
         super::v1::raw_delete,
         super::v1::raw_modify,
         super::v1::raw_search,
+        super::v1::bulk_import,
 
         super::v1_oauth2::oauth2_id_image_delete,
         super::v1_oauth2::oauth2_id_image_post,
         super::v1_oauth2::oauth2_get,
         super::v1_oauth2::oauth2_basic_post,
         super::v1_oauth2::oauth2_public_post,
+        super::v1_oauth2::oauth2_native_post,
         super::v1_oauth2::oauth2_id_get,
         super::v1_oauth2::oauth2_id_patch,
         super::v1_oauth2::oauth2_id_delete,
         super::v1_oauth2::oauth2_id_image_post,
         super::v1_oauth2::oauth2_id_image_delete,
         super::v1_oauth2::oauth2_id_get_basic_secret,
+        super::v1_oauth2::oauth2_id_basic_secret_rotate,
+        super::v1_oauth2::oauth2_id_session_get,
         super::v1_oauth2::oauth2_id_scopemap_post,
         super::v1_oauth2::oauth2_id_scopemap_delete,
         super::v1_oauth2::oauth2_id_sup_scopemap_post,
@@ -74,27 +81,105 @@ impl Modify for SecurityAddon {
         super::v1_oauth2::oauth2_id_claimmap_post,
         super::v1_oauth2::oauth2_id_claimmap_delete,
 
+        super::v1_webhook::webhook_get,
+        super::v1_webhook::webhook_post,
+        super::v1_webhook::webhook_id_get,
+        super::v1_webhook::webhook_id_delete,
+        super::v1_webhook::webhook_id_attr_post,
+        super::v1_webhook::webhook_id_attr_put,
+        super::v1_webhook::webhook_id_attr_delete,
+        super::v1_webhook::webhook_id_secret_get,
+        super::v1_webhook::webhook_id_deliveries_get,
+        super::v1_announcement::announcement_get,
+        super::v1_announcement::announcement_all_get,
+        super::v1_announcement::announcement_all_post,
+        super::v1_announcement::announcement_id_get,
+        super::v1_announcement::announcement_id_delete,
+        super::v1_announcement::announcement_id_attr_put,
+        super::v1_oidc_upstream::oidc_upstream_get,
+        super::v1_oidc_upstream::oidc_upstream_post,
+        super::v1_oidc_upstream::oidc_upstream_id_get,
+        super::v1_oidc_upstream::oidc_upstream_id_delete,
+        super::v1_oidc_upstream::oidc_upstream_id_attr_put,
+        super::v1_oidc_upstream::oidc_upstream_id_attr_delete,
+        super::v1_saml2::saml2_get,
+        super::v1_saml2::saml2_post,
+        super::v1_saml2::saml2_id_get,
+        super::v1_saml2::saml2_id_delete,
+        super::v1_saml2::saml2_id_attr_put,
+        super::v1_saml2::saml2_id_attr_delete,
+        super::v1_saml2::saml2_id_group_map_post,
+        super::v1_saml2::saml2_id_group_map_delete,
+        super::v1_saml2::saml2_metadata_get,
+        super::v1_hbac::hbac_rule_get,
+        super::v1_hbac::hbac_rule_post,
+        super::v1_hbac::hbac_rule_id_get,
+        super::v1_hbac::hbac_rule_id_delete,
+        super::v1_hbac::hbac_rule_id_attr_post,
+        super::v1_hbac::hbac_rule_id_attr_put,
+        super::v1_hbac::hbac_rule_id_attr_delete,
+        super::v1_host::host_get,
+        super::v1_host::host_post,
+        super::v1_host::host_id_get,
+        super::v1_host::host_id_delete,
+        super::v1_host::host_id_attr_post,
+        super::v1_host::host_id_attr_put,
+        super::v1_host::host_id_attr_delete,
+        super::v1_host::host_id_join_token_post,
+        super::v1_host::host_enroll_post,
+        super::v1_host::host_rotate_credential_post,
+        super::v1_host::host_kerberos_keytab_post,
+        super::v1_host::host_kerberos_ticket_post,
+        super::v1_hostgroup::host_group_get,
+        super::v1_hostgroup::host_group_post,
+        super::v1_hostgroup::host_group_id_get,
+        super::v1_hostgroup::host_group_id_delete,
+        super::v1_hostgroup::host_group_id_attr_post,
+        super::v1_hostgroup::host_group_id_attr_put,
+        super::v1_hostgroup::host_group_id_attr_delete,
+
         super::v1_scim::scim_sync_post,
         super::v1_scim::scim_sync_get,
         super::v1_scim::scim_entry_id_get,
         super::v1_scim::scim_person_id_get,
+        super::v1_scim::scim_provision_users_get,
+        super::v1_scim::scim_provision_users_post,
+        super::v1_scim::scim_provision_user_id_get,
+        super::v1_scim::scim_provision_user_id_put,
+        super::v1_scim::scim_provision_user_id_patch,
+        super::v1_scim::scim_provision_user_id_delete,
+        super::v1_scim::scim_provision_groups_get,
+        super::v1_scim::scim_provision_groups_post,
+        super::v1_scim::scim_provision_group_id_get,
+        super::v1_scim::scim_provision_group_id_put,
+        super::v1_scim::scim_provision_group_id_patch,
+        super::v1_scim::scim_provision_group_id_delete,
 
         super::v1::schema_get,
         super::v1::whoami,
         super::v1::whoami_uat,
         super::v1::applinks_get,
+        super::v1::self_ssh_certificate_post,
+        super::v1::self_application_passwords_get,
+        super::v1::self_application_passwords_post,
+        super::v1::self_application_passwords_delete,
+        super::v1::ssh_ca_public_keys_get,
+        super::v1::changes_get,
+        super::v1::audit_get,
         super::v1::schema_attributetype_get,
         super::v1::schema_attributetype_get_id,
         super::v1::schema_classtype_get,
         super::v1::schema_classtype_get_id,
         super::v1::person_get,
         super::v1::person_post,
+        super::v1::person_contractor_post,
         super::v1::service_account_credential_generate,
         super::v1::service_account_api_token_delete,
         super::v1::service_account_api_token_get,
         super::v1::service_account_api_token_post,
         super::v1::person_search_id,
         super::v1::person_id_get,
+        super::v1::person_id_put,
         super::v1::person_id_patch,
         super::v1::person_id_delete,
         super::v1::person_id_get_attr,
@@ -104,9 +189,14 @@ impl Modify for SecurityAddon {
         super::v1::person_get_id_certificate,
         super::v1::person_post_id_certificate,
         super::v1::person_get_id_credential_status,
+        super::v1::person_get_id_credential_softlock,
+        super::v1::person_delete_id_credential_softlock,
         super::v1::person_id_credential_update_get,
         super::v1::person_id_credential_update_intent_get,
+        super::v1::person_id_credential_update_intent_post,
         super::v1::person_id_credential_update_intent_ttl_get,
+        super::v1::person_id_credential_intents_get,
+        super::v1::person_id_credential_intents_delete,
 
         super::v1::service_account_id_ssh_pubkeys_get,
         super::v1::service_account_id_ssh_pubkeys_post,
@@ -120,6 +210,8 @@ impl Modify for SecurityAddon {
         super::v1::person_id_radius_post,
         super::v1::person_id_radius_delete,
         super::v1::person_id_radius_token_get,
+        super::v1::person_id_radius_certificate_post,
+        super::v1::radius_ca_certificate_get,
 
         super::v1::account_id_ssh_pubkeys_get,
         super::v1::account_id_radius_token_post,
@@ -127,11 +219,13 @@ impl Modify for SecurityAddon {
         super::v1::person_id_unix_credential_put,
         super::v1::person_id_unix_credential_delete,
         super::v1::person_identify_user_post,
+        super::v1::person_id_impersonate_post,
         super::v1::service_account_get,
         super::v1::service_account_post,
         super::v1::service_account_get,
         super::v1::service_account_post,
         super::v1::service_account_id_get,
+        super::v1::service_account_id_put,
         super::v1::service_account_id_delete,
         super::v1::service_account_id_patch,
         super::v1::service_account_id_get_attr,
@@ -142,6 +236,8 @@ impl Modify for SecurityAddon {
         super::v1::service_account_api_token_post,
         super::v1::service_account_api_token_get,
         super::v1::service_account_api_token_delete,
+        super::v1::service_account_jwt_public_key_post,
+        super::v1::service_account_jwt_public_key_delete,
         super::v1::service_account_credential_generate,
         super::v1::service_account_id_credential_status_get,
         super::v1::service_account_id_ssh_pubkeys_tag_get,
@@ -156,9 +252,14 @@ impl Modify for SecurityAddon {
         super::v1::account_id_ssh_pubkeys_get,
         super::v1::account_id_ssh_pubkeys_tag_get,
         super::v1::account_id_user_auth_token_get,
+        super::v1::account_id_oauth2_session_get,
+        super::v1::account_id_account_policy_get,
         super::v1::account_user_auth_token_delete,
         super::v1::credential_update_exchange_intent,
+        super::v1::credential_update_exchange_expired,
         super::v1::credential_update_status,
+        super::v1::credential_update_suggest_passphrase,
+        super::v1::credential_update_check_password,
         super::v1::credential_update_update,
         super::v1::credential_update_commit,
         super::v1::credential_update_cancel,
@@ -171,10 +272,16 @@ impl Modify for SecurityAddon {
 
         super::v1::group_id_unix_token_get,
         super::v1::group_id_unix_post,
+        super::v1::group_id_temporary_members_post,
+        super::v1::group_id_membership_request_post,
+        super::v1::group_membership_request_get,
+        super::v1::group_membership_request_id_approve_post,
+        super::v1::group_membership_request_id_deny_post,
         super::v1::group_get,
         super::v1::group_post,
         super::v1::group_search_id,
         super::v1::group_id_get,
+        super::v1::group_id_put,
         super::v1::group_id_patch,
         super::v1::group_id_delete,
         super::v1::group_id_attr_delete,
@@ -182,6 +289,8 @@ impl Modify for SecurityAddon {
         super::v1::group_id_attr_put,
         super::v1::group_id_attr_post,
         super::v1::system_get,
+        super::v1::system_migrations_get,
+        super::v1::system_access_control_preview_get,
         super::v1::system_attr_get,
         super::v1::system_attr_post,
         super::v1::system_attr_put,
@@ -191,6 +300,9 @@ impl Modify for SecurityAddon {
         super::v1::recycle_bin_revive_id_post,
         super::v1::auth,
         super::v1::auth_valid,
+        super::v1::auth_break_glass_challenge_post,
+        super::v1::auth_break_glass_post,
+        super::v1::auth_break_glass_code_post,
         super::v1::logout,
         super::v1::reauth,
         super::v1_scim::sync_account_get,
@@ -204,6 +316,8 @@ impl Modify for SecurityAddon {
         super::v1_scim::sync_account_token_post,
         super::v1_scim::sync_account_token_delete,
         super::v1::debug_ipinfo,
+        super::v1::debug_entry_inspect,
+        super::v1::debug_entry_history,
         super::v1::public_jwk_key_id_get,
 
     ),
@@ -219,31 +333,68 @@ impl Modify for SecurityAddon {
             scim_v1::ScimValue,
             scim_v1::ScimMeta,
             scim_v1::ScimAttr,
+            scim_v1::provision::ScimProvisionUser,
+            scim_v1::provision::ScimProvisionName,
+            scim_v1::provision::ScimProvisionEmail,
+            scim_v1::provision::ScimProvisionMember,
+            scim_v1::provision::ScimProvisionGroup,
+            scim_v1::provision::ScimProvisionUserListResponse,
+            scim_v1::provision::ScimProvisionGroupListResponse,
+            scim_v1::provision::ScimPatchOp,
+            scim_v1::provision::ScimPatchOperation,
+            scim_v1::provision::ScimPatchRequest,
 
+            internal::AccessControlPreview,
+            internal::AccessControlPreviewChange,
+            internal::Announcement,
+            internal::AnnouncementSeverity,
             internal::ApiToken,
+            internal::ApiTokenPermission,
             internal::ApiTokenPurpose,
             internal::BackupCodesView,
+            internal::BulkImportFailure,
+            internal::BulkImportPhase,
+            internal::BulkImportRequest,
+            internal::BulkImportResponse,
             internal::ConsistencyError,
             internal::CreateRequest,
+            internal::DryRunResponse,
             internal::CredentialDetail,
             internal::CredentialDetailType,
             internal::CredentialStatus,
+            internal::CredentialUsageDetail,
+            internal::EntryHistoryEvent,
+            internal::EntryHistoryResponse,
+            internal::EntryInspectAttr,
+            internal::EntryInspectResponse,
+            internal::EntryInspectState,
+            internal::CredentialSoftLockStatus,
             internal::CUExtPortal,
             internal::CUIntentToken,
+            internal::CUIntentTokenRequest,
+            internal::CUIntentTokenInfo,
+            internal::CUIntentTokenState,
+            internal::CUPasswordCheck,
             internal::CURegState,
             internal::CUSessionToken,
             internal::CUStatus,
             internal::DeleteRequest,
+            internal::ErrorCode,
             internal::Filter,
             internal::Group,
             internal::Modify,
             internal::ModifyList,
             internal::ModifyRequest,
+            internal::Oauth2BasicSecretRotateRequest,
             internal::Oauth2ClaimMapJoin,
             internal::OperationError,
+            internal::AccountPolicyLockout,
+            internal::AccountPolicyNetwork,
+            internal::EffectiveAccountPolicy,
             internal::PasskeyDetail,
             internal::PasswordFeedback,
             internal::PluginError,
+            internal::ProblemDetails,
             internal::RadiusAuthToken,
             internal::SchemaError,
             internal::SearchRequest,
@@ -252,8 +403,12 @@ impl Modify for SecurityAddon {
             internal::TotpSecret,
             internal::UatPurpose,
             internal::UserAuthToken,
+            internal::WebhookAction,
+            internal::WebhookDelivery,
+            internal::WebhookDeliveryState,
             v1::AccountUnixExtend,
             v1::ApiTokenGenerate,
+            v1::JwtPublicKeyAdd,
             v1::AuthAllowed,
             v1::AuthCredential,
             v1::AuthIssueSession,
@@ -262,7 +417,18 @@ impl Modify for SecurityAddon {
             v1::AuthResponse,
             v1::AuthState,
             v1::AuthStep,
+            v1::BreakGlassChallenge,
+            v1::BreakGlassCodeRequest,
+            v1::BreakGlassRequest,
+            v1::BreakGlassResponse,
             v1::Entry,
+            v1::FederatedProvider,
+            v1::GroupMembershipRequest,
+            v1::GroupMembershipRequestApprove,
+            v1::GroupMembershipRequestCreate,
+            v1::GroupMembershipRequestStatus,
+            v1::GroupMembershipRequests,
+            v1::GroupTemporaryMembers,
             v1::GroupUnixExtend,
             v1::PublicKeyKindSchema,
             v1::SingleStringRequest,
@@ -273,13 +439,25 @@ impl Modify for SecurityAddon {
             v1::UatPurposeStatus,
             v1::UatStatus,
             v1::UatStatusState,
+            v1::Oauth2SessionStatus,
+            v1::Oauth2SessionRevokeReason,
             v1::UnixGroupToken,
             v1::UnixUserToken,
+            v1::UnixHbacPolicy,
+            v1::UnixHbacRule,
+            v1::HostEnrollRequest,
+            v1::KerberosTicketRequest,
+            v1::KerberosTicket,
+            v1::KerberosKeytab,
             v1::WhoamiResponse,
+            v1::ChangesResponse,
+            v1::EntryApplyResponse,
             internal::CUCredState,
             internal::CURegWarning,
             internal::IdentifyUserResponse,
             internal::AppLink,
+            internal::ApplicationPassword,
+            internal::ApplicationPasswordRequest,
 
             internal::IdentifyUserRequest,
             // terrible workaround for other things
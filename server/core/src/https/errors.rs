@@ -1,14 +1,16 @@
 //! Where we hide the error handling widgets
 //!
 
-use axum::http::header::ACCESS_CONTROL_ALLOW_ORIGIN;
+use axum::http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, CONTENT_TYPE, WWW_AUTHENTICATE};
 use axum::http::{HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use utoipa::ToSchema;
 
-use kanidm_proto::internal::OperationError;
+use kanidm_proto::constants::APPLICATION_PROBLEM_JSON;
+use kanidm_proto::internal::{ErrorCode, OperationError, ProblemDetails};
 
-/// The web app's top level error type, this takes an `OperationError` and converts it into a HTTP response.
+/// The web app's top level error type, this takes an `OperationError` and converts it into a
+/// HTTP response with an RFC 9457 problem details body.
 #[derive(Debug, ToSchema)]
 pub enum WebError {
     /// Something went wrong when doing things.
@@ -34,43 +36,48 @@ impl WebError {
     }
 }
 
+/// The problem details of an error response, held so that the operation id can be added
+/// once the response reaches the kopid middleware.
+#[derive(Clone, Debug)]
+pub(crate) struct ProblemResponse(pub(crate) serde_json::Value);
+
 impl IntoResponse for WebError {
     fn into_response(self) -> Response {
-        match self {
+        let problem = match self {
             WebError::InternalServerError(inner) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, inner).into_response()
+                ProblemDetails::new(ErrorCode::InternalError, Some(inner))
             }
-            WebError::OperationError(inner) => {
-                let (code, headers) = match &inner {
-                    OperationError::NotAuthenticated | OperationError::SessionExpired => {
-                        // https://datatracker.ietf.org/doc/html/rfc7235#section-4.1
-                        (
-                            StatusCode::UNAUTHORIZED,
-                            Some([("WWW-Authenticate", "Bearer"); 1]),
-                        )
-                    }
-                    OperationError::SystemProtectedObject | OperationError::AccessDenied => {
-                        (StatusCode::FORBIDDEN, None)
-                    }
-                    OperationError::NoMatchingEntries => (StatusCode::NOT_FOUND, None),
-                    OperationError::PasswordQuality(_)
-                    | OperationError::EmptyRequest
-                    | OperationError::InvalidAttribute(_)
-                    | OperationError::InvalidAttributeName(_)
-                    | OperationError::SchemaViolation(_)
-                    | OperationError::CU0003WebauthnUserNotVerified
-                    | OperationError::VL0001ValueSshPublicKeyString => {
-                        (StatusCode::BAD_REQUEST, None)
-                    }
-                    _ => (StatusCode::INTERNAL_SERVER_ERROR, None),
-                };
-                let body = serde_json::to_string(&inner).unwrap_or(inner.to_string());
+            WebError::OperationError(inner) => ProblemDetails::from(inner),
+        };
+
+        let code =
+            StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
 
-                match headers {
-                    Some(headers) => (code, headers, body).into_response(),
-                    None => (code, body).into_response(),
-                }
+        let body = match serde_json::to_value(&problem) {
+            Ok(body) => body,
+            Err(err) => {
+                error!(?err, "Unable to serialise problem details");
+                return (code, problem.code.to_string()).into_response();
             }
+        };
+
+        let mut res = (
+            code,
+            [(CONTENT_TYPE, APPLICATION_PROBLEM_JSON)],
+            body.to_string(),
+        )
+            .into_response();
+
+        if matches!(
+            problem.code,
+            ErrorCode::NotAuthenticated | ErrorCode::SessionExpired
+        ) {
+            // https://datatracker.ietf.org/doc/html/rfc7235#section-4.1
+            res.headers_mut()
+                .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
         }
+
+        res.extensions_mut().insert(ProblemResponse(body));
+        res
     }
 }
@@ -3,7 +3,8 @@ use axum::{
     extract::connect_info::{ConnectInfo, Connected},
     extract::FromRequestParts,
     http::{
-        header::HeaderName, header::AUTHORIZATION as AUTHORISATION, request::Parts, StatusCode,
+        header::HeaderName, header::AUTHORIZATION as AUTHORISATION, header::USER_AGENT,
+        request::Parts, StatusCode,
     },
     serve::IncomingStream,
     RequestPartsExt,
@@ -11,8 +12,10 @@ use axum::{
 
 use axum_extra::extract::cookie::CookieJar;
 
-use kanidm_proto::constants::X_FORWARDED_FOR;
-use kanidm_proto::internal::COOKIE_BEARER_TOKEN;
+use kanidm_proto::constants::{DPOP, X_FORWARDED_FOR};
+use kanidm_proto::internal::{COOKIE_BEARER_TOKEN, COOKIE_SESSION_PROOF};
+use kanidmd_lib::idm::dpop::DPoPProof;
+use kanidmd_lib::idm::session_binding::SessionProof;
 use kanidmd_lib::prelude::{ClientAuthInfo, ClientCertInfo, Source};
 // Re-export
 pub use kanidmd_lib::idm::server::DomainInfoRead;
@@ -21,12 +24,17 @@ use compact_jwt::JwsCompact;
 use std::str::FromStr;
 
 use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::Ordering;
 
 use crate::https::ServerState;
 
 #[allow(clippy::declare_interior_mutable_const)]
 const X_FORWARDED_FOR_HEADER: HeaderName = HeaderName::from_static(X_FORWARDED_FOR);
 
+/// User agents are only kept to help people recognise their sessions, so long values are
+/// truncated.
+const USER_AGENT_MAX_LEN: usize = 256;
+
 pub struct TrustedClientIp(pub IpAddr);
 
 #[async_trait]
@@ -53,7 +61,7 @@ impl FromRequestParts<ServerState> for TrustedClientIp {
                 )
             })?;
 
-        let ip_addr = if state.trust_x_forward_for {
+        let ip_addr = if state.trust_x_forward_for.load(Ordering::Relaxed) {
             if let Some(x_forward_for) = parts.headers.get(X_FORWARDED_FOR_HEADER) {
                 // X forward for may be comma separated.
                 let first = x_forward_for
@@ -108,7 +116,7 @@ impl FromRequestParts<ServerState> for VerifiedClientInformation {
                 )
             })?;
 
-        let ip_addr = if state.trust_x_forward_for {
+        let ip_addr = if state.trust_x_forward_for.load(Ordering::Relaxed) {
             if let Some(x_forward_for) = parts.headers.get(X_FORWARDED_FOR_HEADER) {
                 // X forward for may be comma separated.
                 let first = x_forward_for
@@ -149,7 +157,9 @@ impl FromRequestParts<ServerState> for VerifiedClientInformation {
 
                 if authz_type == "basic" {
                     (Some(authz_data.to_string()), None)
-                } else if authz_type == "bearer" {
+                } else if authz_type == "bearer" || authz_type == "dpop" {
+                    // A DPoP bound token is presented with the dpop scheme, and is checked
+                    // against the proof in the DPoP header.
                     if let Ok(jwsc) = JwsCompact::from_str(authz_data) {
                         (None, Some(jwsc))
                     } else {
@@ -174,11 +184,44 @@ impl FromRequestParts<ServerState> for VerifiedClientInformation {
             (None, maybe_bearer)
         };
 
+        let dpop = parts
+            .headers
+            .get(DPOP)
+            // An invalid proof is kept so that the request is rejected, rather than
+            // continuing without a proof.
+            .map(|header| DPoPProof {
+                proof: header.to_str().unwrap_or_default().to_string(),
+                method: parts.method.to_string(),
+                path: parts.uri.path().to_string(),
+            });
+
+        // The browser keeps a proof of the key that its session is bound to in a cookie, as
+        // it can't add headers to navigations.
+        let session_proof = CookieJar::from_headers(&parts.headers)
+            .get(COOKIE_SESSION_PROOF)
+            .map(|c| SessionProof(c.value().to_string()));
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|header| header.to_str().ok())
+            .map(|user_agent| {
+                user_agent
+                    .chars()
+                    .filter(|c| !c.is_control())
+                    .take(USER_AGENT_MAX_LEN)
+                    .collect::<String>()
+            })
+            .filter(|user_agent| !user_agent.is_empty());
+
         Ok(VerifiedClientInformation(ClientAuthInfo {
             source: Source::Https(ip_addr),
             bearer_token,
             basic_authz,
             client_cert,
+            dpop,
+            session_proof,
+            user_agent,
         }))
     }
 }
@@ -1,13 +1,26 @@
 use axum::extract::State;
-use axum::http::header::CONTENT_TYPE;
-use axum::response::{IntoResponse, Redirect};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
 use axum::{Extension, Json};
+use kanidm_proto::internal::{HealthReport, SelfTestCheck, SelfTestItem, SelfTestStatus};
+use kanidmd_lib::idm::authmetrics::{AuthFunnelStep, AUTH_METRICS_MECHS};
+use kanidmd_lib::metrics::{CacheKind, LdapOperation, TokenGrant};
+use kanidmd_lib::prelude::duration_from_epoch_now;
 use kanidmd_lib::status::StatusRequestEvent;
+use openssl::memcmp;
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 
+use super::middleware::load_shedding::RequestPriority;
 use super::middleware::KOpId;
 use super::views::constants::Urls;
 use super::ServerState;
 
+/// Report a degraded TLS certificate when it is this close to expiry.
+const TLS_CERT_EXPIRY_WARNING: Duration = Duration::from_secs(14 * 86400);
+
 #[utoipa::path(
     get,
     path = "/status",
@@ -31,6 +44,397 @@ pub async fn status(
         .into()
 }
 
+#[utoipa::path(
+    get,
+    path = "/status/live",
+    responses(
+        (status = 200, description = "Ok", content_type = "application/json"),
+    ),
+    tag = "system",
+)]
+/// Liveness endpoint, returns a health report when the server is able to respond to requests.
+pub async fn status_live() -> Json<HealthReport> {
+    Json(HealthReport::from(Vec::new()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/status/ready",
+    responses(
+        (status = 200, description = "Ok", content_type = "application/json"),
+        (status = 503, description = "Service Unavailable", content_type = "application/json"),
+    ),
+    tag = "system",
+)]
+/// Readiness endpoint, which checks the database, key providers, replication and the TLS
+/// certificate. Degraded checks are reported with a status of `Warn`, and the server is
+/// only considered unavailable if a check has failed.
+pub async fn status_ready(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+) -> Response {
+    let mut checks = state.qe_r_ref.handle_readiness_check(kopid.eventid).await;
+
+    let now = duration_from_epoch_now();
+    checks.extend(replication_check(&state, now));
+    checks.extend(tls_certificate_check(&state, now));
+
+    let report = HealthReport::from(checks);
+    let status = match report.status {
+        SelfTestStatus::Fail => StatusCode::SERVICE_UNAVAILABLE,
+        SelfTestStatus::Pass | SelfTestStatus::Warn => StatusCode::OK,
+    };
+
+    (status, Json(report)).into_response()
+}
+
+fn replication_check(state: &ServerState, now: Duration) -> Option<SelfTestItem> {
+    let stale_threshold = state.repl_stale_threshold?;
+
+    let item = match state.qe_r_ref.idms.server_metrics().replication {
+        Some(repl) if now.saturating_sub(repl.last_success) <= stale_threshold => SelfTestItem {
+            check: SelfTestCheck::Replication,
+            status: SelfTestStatus::Pass,
+            detail: format!("replicated {}s behind supplier", repl.lag.as_secs()),
+        },
+        Some(repl) => SelfTestItem {
+            check: SelfTestCheck::Replication,
+            status: SelfTestStatus::Warn,
+            detail: format!(
+                "last successful replication was {}s ago",
+                now.saturating_sub(repl.last_success).as_secs()
+            ),
+        },
+        None => SelfTestItem {
+            check: SelfTestCheck::Replication,
+            status: SelfTestStatus::Warn,
+            detail: "has not replicated from a supplier".to_string(),
+        },
+    };
+    Some(item)
+}
+
+fn tls_certificate_check(state: &ServerState, now: Duration) -> Option<SelfTestItem> {
+    let not_after = match state.tls_not_after.load(Ordering::Relaxed) {
+        0 => return None,
+        secs => Duration::from_secs(secs),
+    };
+
+    let item = match not_after.checked_sub(now) {
+        Some(remaining) if remaining > TLS_CERT_EXPIRY_WARNING => SelfTestItem {
+            check: SelfTestCheck::TlsCertificate,
+            status: SelfTestStatus::Pass,
+            detail: format!("expires in {} days", remaining.as_secs() / 86400),
+        },
+        Some(remaining) => SelfTestItem {
+            check: SelfTestCheck::TlsCertificate,
+            status: SelfTestStatus::Warn,
+            detail: format!("expires in {}s", remaining.as_secs()),
+        },
+        None => SelfTestItem {
+            check: SelfTestCheck::TlsCertificate,
+            status: SelfTestStatus::Fail,
+            detail: "certificate has expired".to_string(),
+        },
+    };
+    Some(item)
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Ok", content_type = "text/plain"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Metrics are not served on this listener"),
+    ),
+    security(("token_jwt" = [])),
+    tag = "system",
+)]
+/// Metrics endpoint in the Prometheus text format. On the main listener this requires the
+/// bearer token from the metrics configuration.
+pub async fn metrics(State(state): State<ServerState>, headers: HeaderMap) -> Response {
+    let Some(expected) = state.metrics_bearer_token.as_deref() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let presented = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split_once(' '))
+        .filter(|(authz_type, _)| authz_type.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token.as_bytes());
+
+    match presented {
+        Some(token) if token.len() == expected.len() && memcmp::eq(token, expected.as_bytes()) => {
+            render_metrics(&state).into_response()
+        }
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+/// The metrics endpoint of the dedicated metrics listener. Access to this is controlled by the
+/// address that it is bound to.
+pub(crate) async fn metrics_listener(State(state): State<ServerState>) -> impl IntoResponse {
+    render_metrics(&state)
+}
+
+fn render_metrics(state: &ServerState) -> impl IntoResponse {
+    let funnel = state.qe_r_ref.idms.auth_funnel_metrics();
+    let server = state.qe_r_ref.idms.server_metrics();
+
+    let mut body = String::new();
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_auth_initiated_total Authentication sessions that were started."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_auth_initiated_total counter");
+    let _ = writeln!(body, "kanidm_auth_initiated_total {}", funnel.initiated);
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_auth_step_total Authentication sessions that reached a step with a mechanism."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_auth_step_total counter");
+    for mech in AUTH_METRICS_MECHS.iter() {
+        for step in AuthFunnelStep::ALL {
+            let _ = writeln!(
+                body,
+                "kanidm_auth_step_total{{mech=\"{}\",step=\"{}\"}} {}",
+                mech.to_value(),
+                step.as_str(),
+                funnel.get(mech, step)
+            );
+        }
+    }
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_oauth2_tokens_issued_total OAuth2 tokens that were issued by each grant."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_oauth2_tokens_issued_total counter");
+    for grant in TokenGrant::ALL {
+        let _ = writeln!(
+            body,
+            "kanidm_oauth2_tokens_issued_total{{grant=\"{}\"}} {}",
+            grant.as_str(),
+            server.tokens_issued(grant)
+        );
+    }
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_ldap_operations_total LDAP operations that were requested by clients."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_ldap_operations_total counter");
+    for op in LdapOperation::ALL {
+        let _ = writeln!(
+            body,
+            "kanidm_ldap_operations_total{{op=\"{}\"}} {}",
+            op.as_str(),
+            server.ldap_operations(op)
+        );
+    }
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_requests_admitted_total Requests that were admitted by admission control."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_requests_admitted_total counter");
+    for priority in RequestPriority::ALL {
+        let (admitted, _) = state.admission.counts(priority);
+        let _ = writeln!(
+            body,
+            "kanidm_requests_admitted_total{{priority=\"{}\"}} {}",
+            priority.as_str(),
+            admitted
+        );
+    }
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_requests_shed_total Requests that were refused because the server was overloaded."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_requests_shed_total counter");
+    for priority in RequestPriority::ALL {
+        let (_, shed) = state.admission.counts(priority);
+        let _ = writeln!(
+            body,
+            "kanidm_requests_shed_total{{priority=\"{}\"}} {}",
+            priority.as_str(),
+            shed
+        );
+    }
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_search_duration_seconds The time taken to process searches."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_search_duration_seconds histogram");
+    for (bound_us, count) in server.search_buckets() {
+        let _ = writeln!(
+            body,
+            "kanidm_search_duration_seconds_bucket{{le=\"{}\"}} {}",
+            Duration::from_micros(bound_us).as_secs_f64(),
+            count
+        );
+    }
+    let _ = writeln!(
+        body,
+        "kanidm_search_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+        server.search_count()
+    );
+    let _ = writeln!(
+        body,
+        "kanidm_search_duration_seconds_sum {}",
+        server.search_sum.as_secs_f64()
+    );
+    let _ = writeln!(
+        body,
+        "kanidm_search_duration_seconds_count {}",
+        server.search_count()
+    );
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_cache_hits_total Lookups that were found in the in-memory cache."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_cache_hits_total counter");
+    for cache in CacheKind::ALL {
+        let _ = writeln!(
+            body,
+            "kanidm_cache_hits_total{{cache=\"{}\"}} {}",
+            cache.as_str(),
+            server.cache_hits(cache)
+        );
+    }
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_cache_misses_total Lookups that had to be read from the database."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_cache_misses_total counter");
+    for cache in CacheKind::ALL {
+        let _ = writeln!(
+            body,
+            "kanidm_cache_misses_total{{cache=\"{}\"}} {}",
+            cache.as_str(),
+            server.cache_misses(cache)
+        );
+    }
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_cache_evictions_total Items that were removed from the cache to make space."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_cache_evictions_total counter");
+    for cache in CacheKind::ALL {
+        let _ = writeln!(
+            body,
+            "kanidm_cache_evictions_total{{cache=\"{}\"}} {}",
+            cache.as_str(),
+            server.cache_evictions(cache)
+        );
+    }
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_cache_capacity The number of items the cache can hold."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_cache_capacity gauge");
+    for cache in CacheKind::ALL {
+        let _ = writeln!(
+            body,
+            "kanidm_cache_capacity{{cache=\"{}\"}} {}",
+            cache.as_str(),
+            server.cache_capacity(cache)
+        );
+    }
+
+    if let Some(repl) = server.replication {
+        let _ = writeln!(
+            body,
+            "# HELP kanidm_replication_lag_seconds How far behind its supplier this server was at the last successful replication."
+        );
+        let _ = writeln!(body, "# TYPE kanidm_replication_lag_seconds gauge");
+        let _ = writeln!(
+            body,
+            "kanidm_replication_lag_seconds {}",
+            repl.lag.as_secs_f64()
+        );
+
+        let _ = writeln!(
+            body,
+            "# HELP kanidm_replication_last_success_timestamp_seconds When the last successful replication occurred."
+        );
+        let _ = writeln!(
+            body,
+            "# TYPE kanidm_replication_last_success_timestamp_seconds gauge"
+        );
+        let _ = writeln!(
+            body,
+            "kanidm_replication_last_success_timestamp_seconds {}",
+            repl.last_success.as_secs()
+        );
+    }
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_recycled_purged_total Recycled entries that were purged to tombstones."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_recycled_purged_total counter");
+    let _ = writeln!(
+        body,
+        "kanidm_recycled_purged_total {}",
+        server.recycled_purged
+    );
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_tombstones_reaped_total Tombstones that were removed after leaving the changelog."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_tombstones_reaped_total counter");
+    let _ = writeln!(
+        body,
+        "kanidm_tombstones_reaped_total {}",
+        server.tombstones_reaped
+    );
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_database_reclaimed_bytes_total Space returned to the filesystem by compaction."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_database_reclaimed_bytes_total counter");
+    let _ = writeln!(
+        body,
+        "kanidm_database_reclaimed_bytes_total {}",
+        server.compaction_reclaimed_bytes
+    );
+
+    let _ = writeln!(
+        body,
+        "# HELP kanidm_database_free_bytes Space free within the database after the last compaction."
+    );
+    let _ = writeln!(body, "# TYPE kanidm_database_free_bytes gauge");
+    let _ = writeln!(body, "kanidm_database_free_bytes {}", server.db_free_bytes);
+
+    match std::fs::metadata(&state.db_path) {
+        Ok(md) => {
+            let _ = writeln!(
+                body,
+                "# HELP kanidm_database_size_bytes The size of the database file."
+            );
+            let _ = writeln!(body, "# TYPE kanidm_database_size_bytes gauge");
+            let _ = writeln!(body, "kanidm_database_size_bytes {}", md.len());
+        }
+        Err(err) => {
+            debug!(?err, db_path = ?state.db_path, "Unable to read database size");
+        }
+    }
+
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
 #[utoipa::path(
     get,
     path = "/robots.txt",
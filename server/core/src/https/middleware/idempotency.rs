@@ -0,0 +1,443 @@
+//! Idempotency keys for write requests. When a client sends an `Idempotency-Key` header with a
+//! write, the outcome is kept for a retention window. A retry of the request after a network
+//! failure is answered with the kept outcome, rather than applying the write a second time.
+//!
+//! Outcomes are only kept in the memory of this server, and only for authenticated requests.
+//! They are scoped to the session that made the request so that they can't be replayed to
+//! anyone else, and each session may only keep a limited number of outcomes so that one client
+//! can't crowd out others.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{FromRequestParts, State},
+    http::{header, request::Parts, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use kanidm_proto::constants::{IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED};
+use kanidm_proto::internal::OperationError;
+use openssl::sha::Sha256;
+use uuid::Uuid;
+
+use super::KOpId;
+use crate::https::errors::{ProblemResponse, WebError};
+use crate::https::extractors::VerifiedClientInformation;
+use crate::https::ServerState;
+
+/// How long the outcome of a request is kept for retries.
+const IDEMPOTENCY_RETENTION: Duration = Duration::from_secs(60 * 60);
+/// The maximum number of outcomes that are kept for one session. When this is reached, requests
+/// of that session are processed without keeping their outcome until older outcomes expire.
+const IDEMPOTENCY_MAX_ENTRIES_PER_SESSION: usize = 64;
+/// How often the outcomes of all sessions are checked for expiry.
+const IDEMPOTENCY_SWEEP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// The largest request or response body that may be kept.
+const IDEMPOTENCY_MAX_BODY: usize = 64 * 1024;
+const IDEMPOTENCY_MAX_KEY_LEN: usize = 255;
+
+type Digest = [u8; 32];
+
+#[derive(Clone)]
+struct KeptResponse {
+    status: StatusCode,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+    problem: Option<ProblemResponse>,
+}
+
+enum IdempotencyState {
+    InProgress,
+    Complete(KeptResponse),
+}
+
+struct IdempotencyEntry {
+    request: Digest,
+    expires: Instant,
+    state: IdempotencyState,
+}
+
+enum Begin {
+    Proceed,
+    Replay(KeptResponse),
+    InProgress,
+    Mismatch,
+    Full,
+}
+
+#[derive(Default)]
+struct IdempotencySessions {
+    /// The outcomes of each session, by the digest of their key.
+    sessions: BTreeMap<Uuid, BTreeMap<Digest, IdempotencyEntry>>,
+    next_sweep: Option<Instant>,
+}
+
+#[derive(Default)]
+pub(crate) struct IdempotencyCache {
+    inner: Mutex<IdempotencySessions>,
+}
+
+impl IdempotencyCache {
+    fn begin(&self, session: Uuid, key: Digest, request: Digest, now: Instant) -> Begin {
+        let Ok(mut inner) = self.inner.lock() else {
+            error!("Idempotency cache mutex is poisoned");
+            return Begin::Full;
+        };
+
+        // Sessions that stop sending requests would otherwise keep their outcomes forever.
+        if inner.next_sweep.map(|next| next <= now).unwrap_or(true) {
+            inner.sessions.retain(|_, entries| {
+                entries.retain(|_, entry| entry.expires > now);
+                !entries.is_empty()
+            });
+            inner.next_sweep = Some(now + IDEMPOTENCY_SWEEP_INTERVAL);
+        }
+
+        let entries = inner.sessions.entry(session).or_default();
+
+        if let Some(entry) = entries.get(&key).filter(|entry| entry.expires > now) {
+            return if entry.request != request {
+                Begin::Mismatch
+            } else {
+                match &entry.state {
+                    IdempotencyState::InProgress => Begin::InProgress,
+                    IdempotencyState::Complete(kept) => Begin::Replay(kept.clone()),
+                }
+            };
+        }
+
+        if entries.len() >= IDEMPOTENCY_MAX_ENTRIES_PER_SESSION {
+            entries.retain(|_, entry| entry.expires > now);
+            if entries.len() >= IDEMPOTENCY_MAX_ENTRIES_PER_SESSION {
+                warn!(
+                    ?session,
+                    "Idempotency cache of session is full, outcome of request will not be kept"
+                );
+                return Begin::Full;
+            }
+        }
+
+        entries.insert(
+            key,
+            IdempotencyEntry {
+                request,
+                expires: now + IDEMPOTENCY_RETENTION,
+                state: IdempotencyState::InProgress,
+            },
+        );
+        Begin::Proceed
+    }
+
+    fn complete(&self, session: &Uuid, key: &Digest, kept: KeptResponse) {
+        if let Ok(mut inner) = self.inner.lock() {
+            if let Some(entry) = inner
+                .sessions
+                .get_mut(session)
+                .and_then(|entries| entries.get_mut(key))
+            {
+                entry.state = IdempotencyState::Complete(kept);
+            }
+        }
+    }
+
+    fn abandon(&self, session: &Uuid, key: &Digest) {
+        if let Ok(mut inner) = self.inner.lock() {
+            if let Some(entries) = inner.sessions.get_mut(session) {
+                entries.remove(key);
+                if entries.is_empty() {
+                    inner.sessions.remove(session);
+                }
+            }
+        }
+    }
+}
+
+/// Removes the in progress marker of a request if the request does not complete, such as when
+/// the client disconnects, so that it can be retried.
+struct InProgressGuard {
+    cache: Arc<IdempotencyCache>,
+    session: Uuid,
+    key: Digest,
+    complete: bool,
+}
+
+impl Drop for InProgressGuard {
+    fn drop(&mut self) {
+        if !self.complete {
+            self.cache.abandon(&self.session, &self.key);
+        }
+    }
+}
+
+fn is_write_endpoint(method: &Method, path: &str) -> bool {
+    matches!(
+        *method,
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    ) && (path.starts_with("/v1/") || path.starts_with("/scim/"))
+        // Authentication is a conversation, and its steps must never be replayed.
+        && !path.starts_with("/v1/auth")
+        && !path.starts_with("/v1/reauth")
+}
+
+fn key_digest(key: &str) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_bytes());
+    hasher.finish()
+}
+
+/// The session that the request is authenticated with. Anonymous and unauthenticated requests
+/// have none, and their outcomes are not kept.
+async fn authenticated_session(parts: &mut Parts, state: &ServerState) -> Option<Uuid> {
+    let eventid = parts.extensions.get::<KOpId>()?.eventid;
+    let VerifiedClientInformation(client_auth_info) =
+        VerifiedClientInformation::from_request_parts(parts, state)
+            .await
+            .ok()?;
+
+    state
+        .qe_r_ref
+        .handle_idempotency_scope(client_auth_info, eventid)
+        .await
+        .map_err(|err| {
+            debug!(
+                ?err,
+                "Request is not authenticated, outcome will not be kept"
+            );
+        })
+        .ok()
+}
+
+/// A retry must be the same request as the original.
+fn request_digest(parts: &Parts, body: &[u8]) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str().as_bytes());
+    hasher.update(&[0]);
+    if let Some(path_and_query) = parts.uri.path_and_query() {
+        hasher.update(path_and_query.as_str().as_bytes());
+    }
+    hasher.update(&[0]);
+    hasher.update(body);
+    hasher.finish()
+}
+
+fn replay_response(kept: KeptResponse) -> Response {
+    let mut response = (kept.status, kept.body).into_response();
+    let headers = response.headers_mut();
+    match kept.content_type {
+        Some(content_type) => {
+            headers.insert(header::CONTENT_TYPE, content_type);
+        }
+        None => {
+            headers.remove(header::CONTENT_TYPE);
+        }
+    }
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    if let Some(problem) = kept.problem {
+        response.extensions_mut().insert(problem);
+    }
+    response
+}
+
+/// Replay the outcome of write requests that are retried with the same idempotency key.
+pub async fn idempotency_layer(
+    State(state): State<ServerState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY) else {
+        return next.run(request).await;
+    };
+
+    if !is_write_endpoint(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= IDEMPOTENCY_MAX_KEY_LEN => key.to_string(),
+        _ => return WebError::from(OperationError::HT0004IdempotencyKeyInvalid).into_response(),
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let Some(session) = authenticated_session(&mut parts, &state).await else {
+        return next.run(Request::from_parts(parts, body)).await;
+    };
+
+    let Ok(body) = to_bytes(body, IDEMPOTENCY_MAX_BODY).await else {
+        return WebError::from(OperationError::HT0003IdempotencyRequestTooLarge).into_response();
+    };
+
+    let key = key_digest(&key);
+    let request_digest = request_digest(&parts, &body);
+    let request = Request::from_parts(parts, Body::from(body));
+
+    let cache = &state.idempotency;
+    match cache.begin(session, key, request_digest, Instant::now()) {
+        Begin::Proceed => {}
+        Begin::Full => return next.run(request).await,
+        Begin::Replay(kept) => {
+            debug!("Replaying outcome of idempotent request");
+            return replay_response(kept);
+        }
+        Begin::InProgress => {
+            return WebError::from(OperationError::HT0001IdempotencyKeyInProgress).into_response()
+        }
+        Begin::Mismatch => {
+            return WebError::from(OperationError::HT0002IdempotencyKeyReused).into_response()
+        }
+    }
+
+    let mut guard = InProgressGuard {
+        cache: cache.clone(),
+        session,
+        key,
+        complete: false,
+    };
+
+    let response = next.run(request).await;
+
+    // Server errors are likely to be transient, so the request may be retried. The session or
+    // its access may change before a retry, so refusals are not kept either.
+    let status = response.status();
+    if status.is_server_error()
+        || status == StatusCode::UNAUTHORIZED
+        || status == StatusCode::FORBIDDEN
+    {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(err) => {
+            error!(?err, "Unable to read response body");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    if body.len() <= IDEMPOTENCY_MAX_BODY {
+        cache.complete(
+            &session,
+            &key,
+            KeptResponse {
+                status: parts.status,
+                content_type: parts.headers.get(header::CONTENT_TYPE).cloned(),
+                body: body.clone(),
+                problem: parts.extensions.get::<ProblemResponse>().cloned(),
+            },
+        );
+        guard.complete = true;
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_write_endpoint, Begin, IdempotencyCache, KeptResponse,
+        IDEMPOTENCY_MAX_ENTRIES_PER_SESSION,
+    };
+    use axum::body::Bytes;
+    use axum::http::{Method, StatusCode};
+    use std::time::{Duration, Instant};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_idempotency_write_endpoint() {
+        assert!(is_write_endpoint(&Method::POST, "/v1/person"));
+        assert!(is_write_endpoint(&Method::PATCH, "/scim/v1/Person/test"));
+        assert!(!is_write_endpoint(&Method::GET, "/v1/person"));
+        assert!(!is_write_endpoint(&Method::POST, "/v1/auth"));
+        assert!(!is_write_endpoint(&Method::POST, "/oauth2/token"));
+    }
+
+    #[test]
+    fn test_idempotency_cache() {
+        let cache = IdempotencyCache::default();
+        let now = Instant::now();
+        let session = Uuid::new_v4();
+        let key = [1; 32];
+
+        assert!(matches!(
+            cache.begin(session, key, [2; 32], now),
+            Begin::Proceed
+        ));
+        assert!(matches!(
+            cache.begin(session, key, [2; 32], now),
+            Begin::InProgress
+        ));
+        assert!(matches!(
+            cache.begin(session, key, [3; 32], now),
+            Begin::Mismatch
+        ));
+
+        // The same key of another session is unrelated.
+        assert!(matches!(
+            cache.begin(Uuid::new_v4(), key, [3; 32], now),
+            Begin::Proceed
+        ));
+
+        cache.complete(
+            &session,
+            &key,
+            KeptResponse {
+                status: StatusCode::OK,
+                content_type: None,
+                body: Bytes::from_static(b"null"),
+                problem: None,
+            },
+        );
+        assert!(matches!(
+            cache.begin(session, key, [2; 32], now),
+            Begin::Replay(_)
+        ));
+
+        // Once expired the key may be used again.
+        let later = now + Duration::from_secs(2 * 60 * 60);
+        assert!(matches!(
+            cache.begin(session, key, [3; 32], later),
+            Begin::Proceed
+        ));
+
+        cache.abandon(&session, &key);
+        assert!(matches!(
+            cache.begin(session, key, [2; 32], now),
+            Begin::Proceed
+        ));
+    }
+
+    #[test]
+    fn test_idempotency_cache_session_limit() {
+        let cache = IdempotencyCache::default();
+        let now = Instant::now();
+        let session = Uuid::new_v4();
+
+        for i in 0..IDEMPOTENCY_MAX_ENTRIES_PER_SESSION {
+            let key = [i as u8; 32];
+            assert!(matches!(
+                cache.begin(session, key, [0; 32], now),
+                Begin::Proceed
+            ));
+        }
+        assert!(matches!(
+            cache.begin(session, [255; 32], [0; 32], now),
+            Begin::Full
+        ));
+
+        // A full session doesn't prevent others from keeping outcomes.
+        assert!(matches!(
+            cache.begin(Uuid::new_v4(), [255; 32], [0; 32], now),
+            Begin::Proceed
+        ));
+
+        // Once the outcomes of the session expire it may keep more.
+        let later = now + Duration::from_secs(2 * 60 * 60);
+        assert!(matches!(
+            cache.begin(session, [255; 32], [0; 32], later),
+            Begin::Proceed
+        ));
+    }
+}
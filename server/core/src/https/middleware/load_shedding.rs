@@ -0,0 +1,252 @@
+//! Admission control for the https server. When the server is overloaded, requests are
+//! admitted by priority so that users with existing sessions can keep working while new
+//! authentications and expensive searches are shed.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::cookie::CookieJar;
+use kanidm_proto::constants::uri::{OAUTH2_TOKEN_INTROSPECT_ENDPOINT, V1_AUTH_VALID};
+use tokio::sync::Semaphore;
+use tokio::time::timeout;
+
+use crate::https::ServerState;
+
+/// The number of requests that may be in flight per server thread before requests are queued.
+const ADMISSION_PERMITS_PER_THREAD: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RequestPriority {
+    /// Validation of existing sessions and tokens.
+    High,
+    Normal,
+    /// New authentication attempts and expensive searches.
+    Low,
+}
+
+impl fmt::Display for RequestPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl RequestPriority {
+    pub(crate) const ALL: [RequestPriority; 3] = [
+        RequestPriority::High,
+        RequestPriority::Normal,
+        RequestPriority::Low,
+    ];
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            RequestPriority::High => "high",
+            RequestPriority::Normal => "normal",
+            RequestPriority::Low => "low",
+        }
+    }
+
+    fn classify(method: &Method, path: &str, has_auth_session: bool) -> Self {
+        match (method, path) {
+            (&Method::GET, V1_AUTH_VALID)
+            | (&Method::GET, "/v1/self")
+            | (&Method::GET, "/v1/self/_uat")
+            | (&Method::POST, OAUTH2_TOKEN_INTROSPECT_ENDPOINT) => RequestPriority::High,
+            (_, p)
+                if p.starts_with("/v1/jwk/")
+                    || (p.starts_with("/oauth2/openid/")
+                        && (p.ends_with("/userinfo") || p.ends_with("/public_key.jwk"))) =>
+            {
+                RequestPriority::High
+            }
+            // An auth that is already in progress should be allowed to complete.
+            (&Method::POST, "/v1/auth") if !has_auth_session => RequestPriority::Low,
+            (&Method::GET, "/ui/login") | (&Method::POST, "/ui/login/begin") => {
+                RequestPriority::Low
+            }
+            (&Method::POST, "/v1/raw/search")
+            | (&Method::GET, "/v1/account")
+            | (&Method::GET, "/v1/person")
+            | (&Method::GET, "/v1/group")
+            | (&Method::GET, "/v1/service_account")
+            | (&Method::GET, "/v1/oauth2") => RequestPriority::Low,
+            _ => RequestPriority::Normal,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            RequestPriority::High => 0,
+            RequestPriority::Normal => 1,
+            RequestPriority::Low => 2,
+        }
+    }
+
+    /// How long a request of this priority may wait in the admission queue before it is shed.
+    fn max_wait(self) -> Duration {
+        match self {
+            RequestPriority::High => Duration::from_secs(15),
+            RequestPriority::Normal => Duration::from_secs(5),
+            RequestPriority::Low => Duration::from_millis(500),
+        }
+    }
+}
+
+pub(crate) struct AdmissionControl {
+    /// The total number of requests that may be processed at once.
+    permits: Semaphore,
+    /// Shared by normal and low priority requests. This is less than the total so that a
+    /// quarter of the permits are always reserved for high priority requests.
+    normal_permits: Semaphore,
+    /// Also taken by low priority requests, so that they can only use a quarter of the
+    /// permits and leave the rest to normal priority requests.
+    low_permits: Semaphore,
+    admitted: [AtomicU64; 3],
+    shed: [AtomicU64; 3],
+}
+
+impl AdmissionControl {
+    pub(crate) fn new(threads: usize) -> Self {
+        let total = threads.max(1) * ADMISSION_PERMITS_PER_THREAD;
+        AdmissionControl {
+            permits: Semaphore::new(total),
+            normal_permits: Semaphore::new(total * 3 / 4),
+            low_permits: Semaphore::new((total / 4).max(1)),
+            admitted: Default::default(),
+            shed: Default::default(),
+        }
+    }
+
+    /// The number of requests of this priority that were admitted, and that were shed.
+    pub(crate) fn counts(&self, priority: RequestPriority) -> (u64, u64) {
+        (
+            self.admitted[priority.index()].load(Ordering::Relaxed),
+            self.shed[priority.index()].load(Ordering::Relaxed),
+        )
+    }
+
+    fn record_shed(&self, priority: RequestPriority) {
+        let shed_total = self.shed[priority.index()].fetch_add(1, Ordering::Relaxed) + 1;
+        let admitted_total = self.admitted[priority.index()].load(Ordering::Relaxed);
+        warn!(
+            %priority,
+            shed_total,
+            admitted_total,
+            "Server overloaded, request was shed"
+        );
+    }
+}
+
+fn shed_response() -> Response {
+    let mut response = StatusCode::SERVICE_UNAVAILABLE.into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+    response
+}
+
+/// Admit requests by priority, shedding lower priority requests when the server is overloaded.
+pub async fn load_shedding_layer(
+    State(state): State<ServerState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let admission = &state.admission;
+
+    // Only an auth session that this server signed counts as being in progress, as anyone
+    // can send the header.
+    let has_auth_session = request.method() == Method::POST
+        && request.uri().path() == "/v1/auth"
+        && state
+            .get_current_auth_session_id(
+                request.headers(),
+                &CookieJar::from_headers(request.headers()),
+            )
+            .is_some();
+
+    let priority =
+        RequestPriority::classify(request.method(), request.uri().path(), has_auth_session);
+
+    let admit = async {
+        let low_permit = match priority {
+            RequestPriority::Low => Some(admission.low_permits.acquire().await?),
+            RequestPriority::High | RequestPriority::Normal => None,
+        };
+        let normal_permit = match priority {
+            RequestPriority::Low | RequestPriority::Normal => {
+                Some(admission.normal_permits.acquire().await?)
+            }
+            RequestPriority::High => None,
+        };
+        let permit = admission.permits.acquire().await?;
+        Ok::<_, tokio::sync::AcquireError>((low_permit, normal_permit, permit))
+    };
+
+    let _permits = match timeout(priority.max_wait(), admit).await {
+        Ok(Ok(permits)) => permits,
+        Ok(Err(_)) | Err(_) => {
+            admission.record_shed(priority);
+            return shed_response();
+        }
+    };
+
+    admission.admitted[priority.index()].fetch_add(1, Ordering::Relaxed);
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdmissionControl, RequestPriority};
+    use axum::http::Method;
+
+    #[test]
+    fn test_admission_control_reserves_high_priority() {
+        for threads in [0, 1, 4] {
+            let admission = AdmissionControl::new(threads);
+            let total = admission.permits.available_permits();
+            let normal = admission.normal_permits.available_permits();
+            let low = admission.low_permits.available_permits();
+
+            // Low priority requests also hold a normal permit, so normal and low priority
+            // requests together can't use the permits that are kept for high priority.
+            assert!(normal < total);
+            assert!(low > 0 && low < normal);
+        }
+    }
+
+    #[test]
+    fn test_request_priority_classify() {
+        assert_eq!(
+            RequestPriority::classify(&Method::GET, "/v1/auth/valid", false),
+            RequestPriority::High
+        );
+        assert_eq!(
+            RequestPriority::classify(&Method::GET, "/oauth2/openid/test/userinfo", false),
+            RequestPriority::High
+        );
+        assert_eq!(
+            RequestPriority::classify(&Method::POST, "/v1/auth", false),
+            RequestPriority::Low
+        );
+        assert_eq!(
+            RequestPriority::classify(&Method::POST, "/v1/auth", true),
+            RequestPriority::Normal
+        );
+        assert_eq!(
+            RequestPriority::classify(&Method::POST, "/v1/raw/search", false),
+            RequestPriority::Low
+        );
+        assert_eq!(
+            RequestPriority::classify(&Method::GET, "/v1/person/testperson", false),
+            RequestPriority::Normal
+        );
+    }
+}
@@ -1,15 +1,24 @@
 use axum::{
     body::Body,
-    http::{HeaderValue, Request},
+    http::{
+        header::{ACCEPT_LANGUAGE, CONTENT_LENGTH},
+        HeaderValue, Request,
+    },
     middleware::Next,
     response::Response,
 };
 use kanidm_proto::constants::{KOPID, KVERSION};
 use uuid::Uuid;
 
+use super::errors::ProblemResponse;
+use super::views::i18n::Locale;
+
 pub(crate) mod caching;
 pub(crate) mod compression;
-pub(crate) mod hsts_header;
+pub(crate) mod idempotency;
+pub(crate) mod load_shedding;
+pub(crate) mod rate_limit;
+pub(crate) mod read_only;
 pub(crate) mod security_headers;
 
 // the version middleware injects
@@ -51,18 +60,49 @@ pub async fn are_we_json_yet(request: Request<Body>, next: Next) -> Response {
 pub struct KOpId {
     /// The event correlation ID
     pub eventid: Uuid,
+    /// The language that the client prefers, for rendering the web views.
+    pub(crate) locale: Locale,
+    /// The nonce that inline scripts of the response must carry to be allowed by the content
+    /// security policy. This is unique to each request.
+    pub(crate) csp_nonce: String,
 }
 
 /// This runs at the start of the request, adding an extension with `KOpId` which has useful things inside it.
 #[instrument(level = "trace", name = "kopid_middleware", skip_all)]
 pub async fn kopid_middleware(mut request: Request<Body>, next: Next) -> Response {
-    // generate the event ID
-    let eventid = sketching::tracing_forest::id();
+    // When traces are exported, the event ID is the trace ID of the request, so that the
+    // operation can be found in the tracing system from the id we return to the client. This
+    // is the trace of the caller if they sent a traceparent header, so that their requests
+    // are correlated from end to end. Otherwise the event ID is generated.
+    let eventid = sketching::otel::trace_id(&tracing::Span::current())
+        .map(Uuid::from_bytes)
+        .unwrap_or_else(sketching::tracing_forest::id);
+
+    let locale = request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|hv| hv.to_str().ok())
+        .map(Locale::from_accept_language)
+        .unwrap_or_default();
+
+    let csp_nonce = security_headers::csp_nonce();
 
     // insert the extension so we can pull it out later
-    request.extensions_mut().insert(KOpId { eventid });
+    request.extensions_mut().insert(KOpId {
+        eventid,
+        locale,
+        csp_nonce,
+    });
     let mut response = next.run(request).await;
 
+    // Error responses carry the operation id in their body too, so that it isn't lost by
+    // clients that only keep the body.
+    if let Some(ProblemResponse(mut problem)) = response.extensions_mut().remove() {
+        problem["opid"] = eventid.as_hyphenated().to_string().into();
+        response.headers_mut().remove(CONTENT_LENGTH);
+        *response.body_mut() = Body::from(problem.to_string());
+    }
+
     // This conversion *should never* fail. If it does, rather than panic, we warn and
     // just don't put the id in the response.
     let _ = HeaderValue::from_str(&eventid.as_hyphenated().to_string())
@@ -0,0 +1,388 @@
+//! Rate limiting of authentication attempts. Each source address, and each account that is
+//! being authenticated, has a bucket of tokens that refills at a constant rate. Every attempt
+//! takes a token, and attempts are refused while the bucket is empty. The burst allows a person
+//! to mistype their password a few times without noticing the limit, while a password spray
+//! from one address, or against one account, is slowed to the refill rate.
+//!
+//! The account limit counts attempts by anyone, so anyone who knows the name of an account can
+//! use up its tokens, and the account can't begin a new authentication until they refill. This
+//! is the cost of slowing a spray from many addresses against one account.
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{FromRequestParts, State},
+    http::{header, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::config::AuthRateLimitConfig;
+use crate::https::extractors::TrustedClientIp;
+use crate::https::ServerState;
+
+/// The most buckets that are tracked. When there are this many, the least recently used bucket
+/// is removed to make room, so that the limiter can't grow without bound.
+const RATE_LIMIT_MAX_BUCKETS: usize = 16384;
+
+/// How long to ask a client to wait when the bucket never refills.
+const RATE_LIMIT_MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    seq: u64,
+}
+
+struct Buckets<K> {
+    by_key: BTreeMap<K, Bucket>,
+    /// The buckets in order of when they were last used, so that the oldest can be found
+    /// without scanning every bucket.
+    by_use: BTreeMap<(Instant, u64), K>,
+    seq: u64,
+}
+
+struct TokenBuckets<K> {
+    burst: f64,
+    per_second: f64,
+    max_buckets: usize,
+    buckets: Mutex<Buckets<K>>,
+}
+
+impl<K: Ord + Clone> TokenBuckets<K> {
+    fn new(burst: u32, per_minute: u32) -> Self {
+        Self::with_max_buckets(burst, per_minute, RATE_LIMIT_MAX_BUCKETS)
+    }
+
+    fn with_max_buckets(burst: u32, per_minute: u32, max_buckets: usize) -> Self {
+        TokenBuckets {
+            burst: f64::from(burst.max(1)),
+            per_second: f64::from(per_minute) / 60.0,
+            max_buckets: max_buckets.max(1),
+            buckets: Mutex::new(Buckets {
+                by_key: BTreeMap::new(),
+                by_use: BTreeMap::new(),
+                seq: 0,
+            }),
+        }
+    }
+
+    fn level(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.per_second).min(self.burst)
+    }
+
+    /// Take a token for this key. If none are available, returns how long until the next
+    /// token will be.
+    fn take(&self, key: K, now: Instant) -> Result<(), Duration> {
+        let mut guard = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let buckets = &mut *guard;
+
+        // Buckets that have refilled are the same as a new bucket, so they can be forgotten.
+        // Only the oldest are checked, as they are the first to refill.
+        while let Some(entry) = buckets.by_use.first_entry() {
+            let full = buckets
+                .by_key
+                .get(entry.get())
+                .map(|bucket| self.level(bucket, now) >= self.burst)
+                .unwrap_or(true);
+            if !full {
+                break;
+            }
+            let oldest = entry.remove();
+            buckets.by_key.remove(&oldest);
+        }
+
+        let mut bucket = match buckets.by_key.remove(&key) {
+            Some(bucket) => {
+                buckets.by_use.remove(&(bucket.updated, bucket.seq));
+                bucket
+            }
+            None => {
+                if buckets.by_key.len() >= self.max_buckets {
+                    if let Some((_, oldest)) = buckets.by_use.pop_first() {
+                        buckets.by_key.remove(&oldest);
+                    }
+                }
+                Bucket {
+                    tokens: self.burst,
+                    updated: now,
+                    seq: 0,
+                }
+            }
+        };
+
+        let tokens = self.level(&bucket, now);
+        // A clock that goes backwards must not let a bucket refill again.
+        bucket.updated = bucket.updated.max(now);
+        buckets.seq = buckets.seq.wrapping_add(1);
+        bucket.seq = buckets.seq;
+
+        let result = if tokens >= 1.0 {
+            bucket.tokens = tokens - 1.0;
+            Ok(())
+        } else {
+            bucket.tokens = tokens;
+            if self.per_second > 0.0 {
+                Err(Duration::from_secs_f64((1.0 - tokens) / self.per_second)
+                    .min(RATE_LIMIT_MAX_RETRY_AFTER))
+            } else {
+                Err(RATE_LIMIT_MAX_RETRY_AFTER)
+            }
+        };
+
+        buckets
+            .by_use
+            .insert((bucket.updated, bucket.seq), key.clone());
+        buckets.by_key.insert(key, bucket);
+
+        result
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        let buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        debug_assert_eq!(buckets.by_key.len(), buckets.by_use.len());
+        buckets.by_key.len()
+    }
+}
+
+/// The key of the bucket for a source address. An IPv6 client is usually given a whole /64, so
+/// addresses are limited by their /64 rather than one by one.
+fn source_key(ip: IpAddr) -> IpAddr {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => IpAddr::V4(ip),
+        IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from(
+            u128::from(ip) & 0xffff_ffff_ffff_ffff_0000_0000_0000_0000,
+        )),
+    }
+}
+
+/// The key of the bucket for an account. Accounts are counted by their uuid, so that using its
+/// name, spn or uuid all count against the same account. Names that don't resolve to an account
+/// are counted by name, compared case insensitively.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum AccountKey {
+    Uuid(Uuid),
+    Name(String),
+}
+
+pub(crate) struct AuthRateLimit {
+    source: Option<TokenBuckets<IpAddr>>,
+    account: Option<TokenBuckets<AccountKey>>,
+}
+
+impl AuthRateLimit {
+    pub(crate) fn new(config: &AuthRateLimitConfig) -> Self {
+        if config.enabled {
+            AuthRateLimit {
+                source: Some(TokenBuckets::new(
+                    config.source_burst,
+                    config.source_per_minute,
+                )),
+                account: Some(TokenBuckets::new(
+                    config.account_burst,
+                    config.account_per_minute,
+                )),
+            }
+        } else {
+            AuthRateLimit {
+                source: None,
+                account: None,
+            }
+        }
+    }
+
+    /// Take a token for an authentication attempt from this address.
+    pub(crate) fn check_source(&self, ip: IpAddr) -> Result<(), Duration> {
+        match &self.source {
+            Some(buckets) => buckets.take(source_key(ip), Instant::now()),
+            None => Ok(()),
+        }
+    }
+
+    /// Take a token for an authentication attempt against this account.
+    pub(crate) fn check_account(&self, account: AccountKey) -> Result<(), Duration> {
+        match &self.account {
+            Some(buckets) => buckets.take(account, Instant::now()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Take a token for an authentication attempt against the account with this name. The name is
+/// resolved to the account first, so that the limit can't be avoided by naming the account in a
+/// different way.
+pub(crate) async fn check_account_rate_limit(
+    state: &ServerState,
+    username: &str,
+    eventid: Uuid,
+) -> Result<(), Duration> {
+    if state.auth_rate_limit.account.is_none() {
+        return Ok(());
+    }
+
+    let key = match state
+        .qe_r_ref
+        .handle_auth_account_uuid(username.to_string(), eventid)
+        .await
+    {
+        Ok(Some(uuid)) => AccountKey::Uuid(uuid),
+        Ok(None) => AccountKey::Name(username.trim().to_lowercase()),
+        Err(err) => {
+            // Still limit the attempt, the authentication will report the error.
+            warn!(
+                ?err,
+                "Unable to resolve account for authentication rate limit"
+            );
+            AccountKey::Name(username.trim().to_lowercase())
+        }
+    };
+
+    state.auth_rate_limit.check_account(key)
+}
+
+/// The value of the Retry-After header, which is in whole seconds.
+pub(crate) fn retry_after_secs(retry_after: Duration) -> String {
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    secs.max(1).to_string()
+}
+
+pub(crate) fn rate_limited_response(retry_after: Duration) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after_secs(retry_after))],
+        "Too many authentication attempts, try again later",
+    )
+        .into_response()
+}
+
+fn is_auth_request(method: &Method, path: &str) -> bool {
+    method == Method::POST
+        && (path == "/v1/auth" || path.starts_with("/v1/auth/") || path.starts_with("/ui/login/"))
+}
+
+/// Limit the rate of authentication attempts from each source address. Attempts against each
+/// account are limited by the handlers, since only they know which account is named.
+pub async fn auth_rate_limit_layer(
+    State(state): State<ServerState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if !is_auth_request(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+
+    let ip_addr = match TrustedClientIp::from_request_parts(&mut parts, &state).await {
+        Ok(TrustedClientIp(ip_addr)) => ip_addr,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    if let Err(retry_after) = state.auth_rate_limit.check_source(ip_addr) {
+        warn!(%ip_addr, "Authentication rate limit reached for source address");
+        return rate_limited_response(retry_after);
+    }
+
+    next.run(Request::from_parts(parts, body)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_auth_request, retry_after_secs, source_key, TokenBuckets};
+    use axum::http::Method;
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_auth_rate_limit_token_bucket() {
+        // Burst of 2, refilling one token every second.
+        let buckets = TokenBuckets::new(2, 60);
+        let now = Instant::now();
+
+        assert!(buckets.take("alice", now).is_ok());
+        assert!(buckets.take("alice", now).is_ok());
+        let retry_after = buckets.take("alice", now).unwrap_err();
+        assert_eq!(retry_after_secs(retry_after), "1");
+
+        // Other keys are unaffected.
+        assert!(buckets.take("bob", now).is_ok());
+
+        // Refused attempts don't delay the refill.
+        assert!(buckets
+            .take("alice", now + Duration::from_millis(500))
+            .is_err());
+        assert!(buckets.take("alice", now + Duration::from_secs(1)).is_ok());
+        assert!(buckets.take("alice", now + Duration::from_secs(1)).is_err());
+
+        // The bucket never holds more than the burst.
+        let later = now + Duration::from_secs(3600);
+        assert!(buckets.take("alice", later).is_ok());
+        assert!(buckets.take("alice", later).is_ok());
+        assert!(buckets.take("alice", later).is_err());
+    }
+
+    #[test]
+    fn test_auth_rate_limit_no_refill() {
+        let buckets = TokenBuckets::new(1, 0);
+        let now = Instant::now();
+
+        assert!(buckets.take("alice", now).is_ok());
+        assert_eq!(
+            buckets.take("alice", now + Duration::from_secs(3600)),
+            Err(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn test_auth_rate_limit_bounded() {
+        let buckets = TokenBuckets::with_max_buckets(2, 60, 3);
+        let now = Instant::now();
+
+        assert!(buckets.take("alice", now).is_ok());
+        assert!(buckets.take("alice", now).is_ok());
+        assert!(buckets.take("bob", now).is_ok());
+        assert!(buckets.take("carol", now).is_ok());
+        assert_eq!(buckets.len(), 3);
+
+        // At the limit, the least recently used bucket is removed for a new one.
+        assert!(buckets.take("alice", now).is_err());
+        assert!(buckets.take("dave", now).is_ok());
+        assert_eq!(buckets.len(), 3);
+        assert!(buckets.take("alice", now).is_err());
+
+        // Buckets that have refilled are removed.
+        assert!(buckets.take("erin", now + Duration::from_secs(2)).is_ok());
+        assert_eq!(buckets.len(), 1);
+    }
+
+    #[test]
+    fn test_auth_rate_limit_source_key() {
+        let a: IpAddr = "2001:db8:1:2:aaaa::1".parse().unwrap();
+        let b: IpAddr = "2001:db8:1:2:bbbb::2".parse().unwrap();
+        let c: IpAddr = "2001:db8:1:3::1".parse().unwrap();
+        assert_eq!(source_key(a), source_key(b));
+        assert_ne!(source_key(a), source_key(c));
+
+        let v4: IpAddr = "192.0.2.1".parse().unwrap();
+        let mapped: IpAddr = "::ffff:192.0.2.1".parse().unwrap();
+        assert_eq!(source_key(v4), v4);
+        assert_eq!(source_key(mapped), v4);
+    }
+
+    #[test]
+    fn test_auth_rate_limit_is_auth_request() {
+        assert!(is_auth_request(&Method::POST, "/v1/auth"));
+        assert!(is_auth_request(&Method::POST, "/v1/auth/break_glass/_code"));
+        assert!(is_auth_request(&Method::POST, "/ui/login/begin"));
+        assert!(!is_auth_request(&Method::GET, "/v1/auth/valid"));
+        assert!(!is_auth_request(&Method::GET, "/ui/login"));
+        assert!(!is_auth_request(&Method::POST, "/v1/self"));
+    }
+}
@@ -0,0 +1,114 @@
+//! A read only replica only consumes changes from its replication partners. Requests that would
+//! change the database are referred to a writable server instead, so that the change is made
+//! there and replicated back to this server.
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use kanidm_proto::constants::uri::OAUTH2_TOKEN_INTROSPECT_ENDPOINT;
+use url::Url;
+
+use crate::https::ServerState;
+
+/// If this request may change the database. Authentication creates a session that is stored
+/// on the account, so it is referred to the writable server along with logout.
+fn is_write(method: &Method, path: &str) -> bool {
+    match (method, path) {
+        (&Method::GET, "/v1/logout") | (&Method::GET, "/ui/logout") => true,
+        (&Method::GET, _) | (&Method::HEAD, _) | (&Method::OPTIONS, _) => false,
+        (&Method::POST, "/v1/raw/search") | (&Method::POST, OAUTH2_TOKEN_INTROSPECT_ENDPOINT) => {
+            false
+        }
+        _ => true,
+    }
+}
+
+/// The location of the same request on the writable server.
+fn referral(write_origin: &Url, request: &Request<Body>) -> Option<HeaderValue> {
+    let path_and_query = request
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+
+    write_origin
+        .join(path_and_query)
+        .ok()
+        .and_then(|url| HeaderValue::from_str(url.as_str()).ok())
+}
+
+pub async fn read_only_replica_layer(
+    State(state): State<ServerState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(write_origin) = &state.write_origin else {
+        return next.run(request).await;
+    };
+
+    if !is_write(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    debug!(
+        method = %request.method(),
+        path = %request.uri().path(),
+        "Referring write to {}",
+        write_origin
+    );
+
+    match referral(write_origin, &request) {
+        // A temporary redirect requires the client to repeat the same method and body.
+        Some(location) => (
+            StatusCode::TEMPORARY_REDIRECT,
+            [(header::LOCATION, location)],
+        )
+            .into_response(),
+        None => {
+            error!(%write_origin, "Unable to build a referral to the writable server");
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_write, referral};
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use url::Url;
+
+    #[test]
+    fn test_read_only_replica_is_write() {
+        assert!(!is_write(&Method::GET, "/v1/person"));
+        assert!(!is_write(&Method::POST, "/v1/raw/search"));
+        assert!(!is_write(&Method::POST, "/oauth2/token/introspect"));
+
+        assert!(is_write(&Method::POST, "/v1/auth"));
+        assert!(is_write(&Method::POST, "/v1/person"));
+        assert!(is_write(&Method::PATCH, "/v1/person/demo"));
+        assert!(is_write(&Method::DELETE, "/v1/person/demo"));
+        assert!(is_write(&Method::GET, "/v1/logout"));
+    }
+
+    #[test]
+    fn test_read_only_replica_referral() {
+        let write_origin = Url::parse("https://idm.example.com").expect("Invalid url");
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/v1/person/demo/_attr/mail?x=1")
+            .body(Body::empty())
+            .expect("Invalid request");
+
+        assert_eq!(
+            referral(&write_origin, &request)
+                .as_ref()
+                .and_then(|v| v.to_str().ok()),
+            Some("https://idm.example.com/v1/person/demo/_attr/mail?x=1")
+        );
+    }
+}
@@ -5,23 +5,129 @@ use axum::{
     middleware::Next,
     response::Response,
 };
+use openssl::rand::rand_bytes;
 
+use super::KOpId;
+use crate::config::HttpSecurityConfig;
 use crate::https::ServerState;
 
 const PERMISSIONS_POLICY_VALUE: &str = "fullscreen=(), geolocation=()";
 const X_CONTENT_TYPE_OPTIONS_VALUE: &str = "nosniff";
+const CSP_NONCE_LEN: usize = 16;
+
+/// The security headers that are added to every response. The content security policy is
+/// completed for each response with the nonce of the request.
+#[derive(Debug)]
+pub(crate) struct SecurityHeaders {
+    script_src: String,
+    frame_ancestors: String,
+    hsts: Option<HeaderValue>,
+}
+
+impl SecurityHeaders {
+    /// Build the headers from the configuration and the hashes of the javascript files that
+    /// we serve.
+    pub(crate) fn new(js_hashes: &[String], config: &HttpSecurityConfig) -> Result<Self, ()> {
+        let script_src = js_hashes.iter().fold(String::new(), |mut output, hash| {
+            output.push_str(" 'sha384-");
+            output.push_str(hash);
+            output.push('\'');
+            output
+        });
+
+        // Each source must be a single token, so that it can't add other directives to the policy.
+        if let Some(origin) = config.frame_ancestors.iter().find(|origin| {
+            origin.is_empty()
+                || origin.contains(|c: char| c.is_whitespace() || c == ';' || c == ',')
+        }) {
+            error!(
+                ?origin,
+                "Invalid frame ancestor in the http_security configuration"
+            );
+            return Err(());
+        }
+
+        let frame_ancestors = if config.frame_ancestors.is_empty() {
+            "'none'".to_string()
+        } else {
+            config.frame_ancestors.join(" ")
+        };
+
+        let hsts = if config.hsts_max_age == 0 {
+            None
+        } else {
+            let mut hsts = format!("max-age={}", config.hsts_max_age);
+            if config.hsts_include_subdomains {
+                hsts.push_str("; includeSubDomains");
+            }
+            if config.hsts_preload {
+                hsts.push_str("; preload");
+            }
+            Some(HeaderValue::from_str(&hsts).map_err(|err| {
+                error!(?err, "Unable to generate strict transport security header");
+            })?)
+        };
+
+        let security_headers = SecurityHeaders {
+            script_src,
+            frame_ancestors,
+            hsts,
+        };
+
+        // Check the policy is a valid header now, rather than when we respond.
+        security_headers
+            .content_security_policy(&csp_nonce())
+            .map(|_| security_headers)
+    }
+
+    fn content_security_policy(&self, nonce: &str) -> Result<HeaderValue, ()> {
+        let csp = format!(
+            concat!(
+                "default-src 'self'; ",
+                "base-uri 'self' https:; ",
+                "form-action 'self' https:; ",
+                "frame-ancestors {}; ",
+                "img-src 'self' data:; ",
+                "worker-src 'none'; ",
+                "script-src 'self' 'unsafe-eval' 'nonce-{}'{};",
+            ),
+            self.frame_ancestors, nonce, self.script_src
+        );
+
+        HeaderValue::from_str(&csp).map_err(|err| {
+            error!(?err, "Unable to generate content security policy");
+        })
+    }
+}
+
+/// Generate a new nonce for the content security policy of a response.
+pub(crate) fn csp_nonce() -> String {
+    let mut nonce = [0; CSP_NONCE_LEN];
+    // If the system is unable to provide randomness we can't safely continue.
+    #[allow(clippy::expect_used)]
+    rand_bytes(&mut nonce).expect("Unable to generate a content security policy nonce");
+    hex::encode(nonce)
+}
 
 pub async fn security_headers_layer(
     State(state): State<ServerState>,
     request: Request<Body>,
     next: Next,
 ) -> Response {
+    let nonce = request
+        .extensions()
+        .get::<KOpId>()
+        .map(|kopid| kopid.csp_nonce.clone())
+        .unwrap_or_else(csp_nonce);
+
     // wait for the middleware to come back
     let mut response = next.run(request).await;
 
     // add the Content-Security-Policy header, which defines how contact will be accessed/run based on the source URL
     let headers = response.headers_mut();
-    headers.insert(header::CONTENT_SECURITY_POLICY, state.csp_header);
+    if let Ok(csp) = state.security_headers.content_security_policy(&nonce) {
+        headers.insert(header::CONTENT_SECURITY_POLICY, csp);
+    }
 
     // X-Content-Type-Options tells the browser if it's OK to "sniff" or guess the content type of a response
     //
@@ -49,5 +155,61 @@ pub async fn security_headers_layer(
         HeaderValue::from_static("no-referrer-when-downgrade"),
     );
 
+    // Strict-Transport-Security tells the browser to only connect to us with https.
+    //
+    // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Strict-Transport-Security
+    if let Some(hsts) = state.security_headers.hsts.as_ref() {
+        headers.insert(header::STRICT_TRANSPORT_SECURITY, hsts.clone());
+    }
+
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{csp_nonce, SecurityHeaders};
+    use crate::config::HttpSecurityConfig;
+
+    #[test]
+    fn test_security_headers() {
+        let hashes = vec!["abc".to_string()];
+
+        let headers = SecurityHeaders::new(&hashes, &HttpSecurityConfig::default())
+            .expect("Failed to build security headers");
+        let csp = headers
+            .content_security_policy("1234")
+            .expect("Failed to build content security policy");
+        let csp = csp.to_str().expect("Invalid content security policy");
+        assert!(csp.contains("frame-ancestors 'none';"));
+        assert!(csp.contains("'nonce-1234' 'sha384-abc';"));
+        assert_eq!(
+            headers.hsts.as_ref().and_then(|hv| hv.to_str().ok()),
+            Some("max-age=86400")
+        );
+
+        let config = HttpSecurityConfig {
+            hsts_max_age: 0,
+            frame_ancestors: vec!["https://portal.example.com".to_string()],
+            ..Default::default()
+        };
+        let headers =
+            SecurityHeaders::new(&hashes, &config).expect("Failed to build security headers");
+        let csp = headers
+            .content_security_policy("1234")
+            .expect("Failed to build content security policy");
+        assert!(csp
+            .to_str()
+            .expect("Invalid content security policy")
+            .contains("frame-ancestors https://portal.example.com;"));
+        assert!(headers.hsts.is_none());
+
+        // An origin can't be used to inject other directives.
+        let config = HttpSecurityConfig {
+            frame_ancestors: vec!["https://a.example.com; script-src *".to_string()],
+            ..Default::default()
+        };
+        assert!(SecurityHeaders::new(&hashes, &config).is_err());
+
+        assert_ne!(csp_nonce(), csp_nonce());
+    }
+}
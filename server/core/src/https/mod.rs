@@ -9,21 +9,30 @@ pub(crate) mod middleware;
 mod oauth2;
 pub(crate) mod trace;
 mod v1;
+mod v1_announcement;
 mod v1_domain;
+mod v1_hbac;
+mod v1_host;
+mod v1_hostgroup;
 mod v1_oauth2;
+mod v1_oidc_upstream;
+mod v1_saml2;
 mod v1_scim;
+mod v1_webhook;
 mod views;
 
 use self::extractors::ClientConnInfo;
 use self::javascript::*;
 use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
 use crate::config::{Configuration, ServerRole};
+use crate::crypto;
+use crate::tenant::TenantServer;
 use crate::CoreAction;
 
 use axum::{
     body::Body,
     extract::connect_info::IntoMakeServiceWithConnectInfo,
-    http::{HeaderMap, HeaderValue, Request},
+    http::{header::HOST, HeaderMap, Request},
     middleware::{from_fn, from_fn_with_state},
     response::Redirect,
     routing::*,
@@ -43,7 +52,6 @@ use kanidm_lib_crypto::x509_cert::{der::Decode, x509_public_key_s256, Certificat
 
 use serde::de::DeserializeOwned;
 use sketching::*;
-use std::fmt::Write;
 use tokio::{
     net::{TcpListener, TcpStream},
     sync::broadcast,
@@ -56,9 +64,15 @@ use tower_http::{services::ServeDir, trace::TraceLayer};
 use url::Url;
 use uuid::Uuid;
 
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::io::ErrorKind;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use std::{net::SocketAddr, str::FromStr};
 
 #[derive(Clone)]
@@ -68,12 +82,32 @@ pub struct ServerState {
     pub(crate) qe_r_ref: &'static QueryServerReadV1,
     // Store the token management parts.
     pub(crate) jws_signer: JwsHs256Signer,
-    pub(crate) trust_x_forward_for: bool,
-    pub(crate) csp_header: HeaderValue,
+    /// This can be changed when the configuration is reloaded.
+    pub(crate) trust_x_forward_for: Arc<AtomicBool>,
+    pub(crate) security_headers: Arc<middleware::security_headers::SecurityHeaders>,
     pub(crate) origin: Url,
     pub(crate) domain: String,
     // This is set to true by default, and is only false on integration tests.
     pub(crate) secure_cookies: bool,
+    pub(crate) admission: Arc<middleware::load_shedding::AdmissionControl>,
+    /// Limits on the rate of authentication attempts by source address and account.
+    pub(crate) auth_rate_limit: Arc<middleware::rate_limit::AuthRateLimit>,
+    /// Failed logins per account, and the proof of work challenges issued on the login form.
+    pub(crate) login_challenges: Arc<views::login_challenge::LoginChallenges>,
+    /// The outcomes of write requests that were sent with an idempotency key.
+    pub(crate) idempotency: Arc<middleware::idempotency::IdempotencyCache>,
+    /// When this server is a read only replica, the writable server that writes are referred to.
+    pub(crate) write_origin: Option<Url>,
+    /// The bearer token that is required to read metrics from the main listener. If unset,
+    /// metrics are not served on the main listener.
+    pub(crate) metrics_bearer_token: Option<String>,
+    pub(crate) db_path: PathBuf,
+    /// When serving TLS, the time that the server certificate expires in seconds since the
+    /// unix epoch. This is updated as the certificate is reloaded.
+    pub(crate) tls_not_after: Arc<AtomicU64>,
+    /// How long this server may go without replicating from a supplier before it is reported
+    /// as degraded. This is unset when the server does not pull from any supplier.
+    pub(crate) repl_stale_threshold: Option<Duration>,
 }
 
 impl ServerState {
@@ -140,6 +174,10 @@ pub(crate) fn get_js_files(role: ServerRole) -> Result<Vec<JavaScriptFile>, ()>
             "external/base64.js",
             "modules/cred_update.mjs",
             "pkhtml.js",
+            "pkautofill.js",
+            "loginchallenge.js",
+            "sessionkey.js",
+            "sessionidle.js",
             "style.js",
         ];
 
@@ -164,6 +202,7 @@ pub(crate) fn get_js_files(role: ServerRole) -> Result<Vec<JavaScriptFile>, ()>
     Ok(all_pages)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_https_server(
     config: Configuration,
     jws_signer: JwsHs256Signer,
@@ -173,9 +212,178 @@ pub async fn create_https_server(
     server_message_tx: broadcast::Sender<CoreAction>,
     maybe_tls_acceptor: Option<SslAcceptor>,
     tls_acceptor_reload_rx: mpsc::Receiver<SslAcceptor>,
+    trust_x_forward_for: Arc<AtomicBool>,
+    tenants: Vec<TenantServer>,
 ) -> Result<task::JoinHandle<()>, ()> {
     let rx = server_message_tx.subscribe();
 
+    let tls_not_after = Arc::new(AtomicU64::new(
+        maybe_tls_acceptor
+            .as_ref()
+            .and_then(crypto::tls_acceptor_not_after)
+            .unwrap_or_default(),
+    ));
+
+    let state = server_state(
+        &config,
+        jws_signer,
+        status_ref,
+        qe_w_ref,
+        qe_r_ref,
+        trust_x_forward_for.clone(),
+        tls_not_after.clone(),
+    )?;
+
+    let maybe_metrics_listener = match &config.metrics.bind_address {
+        Some(bind_address) => {
+            let addr = SocketAddr::from_str(bind_address).map_err(|err| {
+                error!(
+                    "Failed to parse metrics bind_address ({:?}) from config: {:?}",
+                    bind_address, err
+                );
+            })?;
+            let app = Router::new()
+                .route("/metrics", get(generic::metrics_listener))
+                .with_state(state.clone())
+                .into_make_service_with_connect_info::<ClientConnInfo>();
+            Some((addr, app))
+        }
+        None => None,
+    };
+
+    let app = https_router(&config, state);
+
+    // When tenants are served, each request is directed to the domain of the host it was
+    // sent to. Any other host is served by the primary domain.
+    let app = if tenants.is_empty() {
+        app
+    } else {
+        let mut tenant_routers = BTreeMap::new();
+        for tenant in tenants {
+            let jws_signer = JwsHs256Signer::generate_hs256()
+                .map(|signer| signer.set_sign_option_embed_kid(false))
+                .map_err(|err| {
+                    error!(?err, domain = %tenant.config.domain, "Unable to setup tenant jws signer");
+                })?;
+
+            let tenant_state = server_state(
+                &tenant.config,
+                jws_signer,
+                status_ref,
+                tenant.qe_w_ref,
+                tenant.qe_r_ref,
+                trust_x_forward_for.clone(),
+                tls_not_after.clone(),
+            )?;
+
+            info!(domain = %tenant.config.domain, "Serving tenant at {}", tenant.config.origin);
+            tenant_routers.insert(tenant.host, https_router(&tenant.config, tenant_state));
+        }
+
+        Router::new().fallback_service(TenantRouter {
+            primary: app,
+            tenants: Arc::new(tenant_routers),
+        })
+    };
+
+    // the connect_info bit here lets us pick up the remote address of the client
+    let app = app.into_make_service_with_connect_info::<ClientConnInfo>();
+
+    let addr = SocketAddr::from_str(&config.address).map_err(|err| {
+        error!(
+            "Failed to parse address ({:?}) from config: {:?}",
+            config.address, err
+        );
+    })?;
+
+    info!("Starting the web server...");
+
+    let metrics_rx = server_message_tx.subscribe();
+
+    let handle = match maybe_tls_acceptor {
+        Some(tls_acceptor) => {
+            let listener = match TcpListener::bind(addr).await {
+                Ok(l) => l,
+                Err(err) => {
+                    error!(?err, "Failed to bind tcp listener");
+                    return Err(());
+                }
+            };
+            task::spawn(server_loop(
+                tls_acceptor,
+                listener,
+                app,
+                rx,
+                server_message_tx,
+                tls_acceptor_reload_rx,
+                tls_not_after,
+            ))
+        }
+        None => task::spawn(server_loop_plaintext(addr, app, rx)),
+    };
+
+    match maybe_metrics_listener {
+        Some((metrics_addr, metrics_app)) => {
+            info!("Starting the metrics server on {} ...", metrics_addr);
+            let metrics_handle =
+                task::spawn(metrics_server_loop(metrics_addr, metrics_app, metrics_rx));
+            Ok(task::spawn(async move {
+                let _ = tokio::join!(handle, metrics_handle);
+            }))
+        }
+        None => Ok(handle),
+    }
+}
+
+/// Directs each request to the router of the tenant that serves the host it was sent to.
+#[derive(Clone)]
+struct TenantRouter {
+    primary: Router,
+    tenants: Arc<BTreeMap<String, Router>>,
+}
+
+impl Service<Request<Body>> for TenantRouter {
+    type Response = <Router as Service<Request<Body>>>::Response;
+    type Error = Infallible;
+    type Future = <Router as Service<Request<Body>>>::Future;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Routers are always ready.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // HTTP/2 sends the host in the uri, and HTTP/1.1 sends it in the host header.
+        let host = request
+            .uri()
+            .host()
+            .or_else(|| {
+                request
+                    .headers()
+                    .get(HOST)
+                    .and_then(|hv| hv.to_str().ok())
+                    .and_then(|host| host.split(':').next())
+            })
+            .map(|host| host.to_lowercase());
+
+        let mut router = host
+            .and_then(|host| self.tenants.get(&host))
+            .unwrap_or(&self.primary)
+            .clone();
+
+        router.call(request)
+    }
+}
+
+fn server_state(
+    config: &Configuration,
+    jws_signer: JwsHs256Signer,
+    status_ref: &'static StatusActor,
+    qe_w_ref: &'static QueryServerWriteV1,
+    qe_r_ref: &'static QueryServerReadV1,
+    trust_x_forward_for: Arc<AtomicBool>,
+    tls_not_after: Arc<AtomicU64>,
+) -> Result<ServerState, ()> {
     let all_js_files = get_js_files(config.role)?;
     // set up the CSP headers
     // script-src 'self'
@@ -187,31 +395,10 @@ pub async fn create_https_server(
         .map(|f| f.hash)
         .collect::<Vec<String>>();
 
-    let js_checksums: String = js_directives
-        .iter()
-        .fold(String::new(), |mut output, value| {
-            let _ = write!(output, " 'sha384-{}'", value);
-            output
-        });
-
-    let csp_header = format!(
-        concat!(
-            "default-src 'self'; ",
-            "base-uri 'self' https:; ",
-            "form-action 'self' https:;",
-            "frame-ancestors 'none'; ",
-            "img-src 'self' data:; ",
-            "worker-src 'none'; ",
-            "script-src 'self' 'unsafe-eval'{};",
-        ),
-        js_checksums
-    );
-
-    let csp_header = HeaderValue::from_str(&csp_header).map_err(|err| {
-        error!(?err, "Unable to generate content security policy");
-    })?;
-
-    let trust_x_forward_for = config.trust_x_forward_for;
+    let security_headers = Arc::new(middleware::security_headers::SecurityHeaders::new(
+        &js_directives,
+        &config.http_security,
+    )?);
 
     let origin = Url::parse(&config.origin)
         // Should be impossible!
@@ -219,18 +406,52 @@ pub async fn create_https_server(
             error!(?err, "Unable to parse origin URL - refusing to start. You must correct the value for origin. {:?}", config.origin);
         })?;
 
-    let state = ServerState {
+    let write_origin = match (config.role, &config.write_origin) {
+        (ServerRole::ReadOnlyReplica, Some(write_origin)) => {
+            Some(Url::parse(write_origin).map_err(|err| {
+                error!(?err, "Unable to parse write_origin URL - refusing to start. You must correct the value for write_origin. {:?}", write_origin);
+            })?)
+        }
+        (ServerRole::ReadOnlyReplica, None) => {
+            error!("write_origin must be set when the role is read_only_replica - refusing to start.");
+            return Err(());
+        }
+        _ => None,
+    };
+
+    Ok(ServerState {
         status_ref,
         qe_w_ref,
         qe_r_ref,
         jws_signer,
         trust_x_forward_for,
-        csp_header,
+        security_headers,
         origin,
         domain: config.domain.clone(),
         secure_cookies: config.integration_test_config.is_none(),
-    };
+        admission: Arc::new(middleware::load_shedding::AdmissionControl::new(
+            config.threads,
+        )),
+        auth_rate_limit: Arc::new(middleware::rate_limit::AuthRateLimit::new(
+            &config.auth_rate_limit,
+        )),
+        login_challenges: Arc::new(views::login_challenge::LoginChallenges::new(
+            config.login_challenge.as_ref(),
+        )),
+        idempotency: Arc::default(),
+        write_origin,
+        metrics_bearer_token: config.metrics.bearer_token.clone(),
+        db_path: PathBuf::from(&config.db_path),
+        tls_not_after,
+        repl_stale_threshold: config
+            .repl_config
+            .as_ref()
+            .and_then(|repl| repl.get_consumer_stale_threshold()),
+    })
+}
 
+/// The routes, middleware and state that serve the domain of this configuration.
+fn https_router(config: &Configuration, state: ServerState) -> Router {
     let static_routes = match config.role {
         ServerRole::WriteReplica | ServerRole::ReadOnlyReplica => {
             Router::new()
@@ -289,17 +510,44 @@ pub async fn create_https_server(
             state.clone(),
             middleware::security_headers::security_headers_layer,
         ))
-        .layer(from_fn(middleware::version_middleware))
-        .layer(from_fn(
-            middleware::hsts_header::strict_transport_security_layer,
-        ));
+        .layer(from_fn(middleware::version_middleware));
 
     // layer which checks the responses have a content-type of JSON when we're in debug mode
     #[cfg(any(test, debug_assertions))]
     let app = app.layer(from_fn(middleware::are_we_json_yet));
 
-    let app = app
-        .route("/status", get(generic::status))
+    // Admission control is applied before requests reach any handler so that when overloaded
+    // we shed low priority work as early as possible. Status and metrics are exempt.
+    let app = app.layer(from_fn_with_state(
+        state.clone(),
+        middleware::load_shedding::load_shedding_layer,
+    ));
+
+    // Authentication attempts over the rate limit are refused before they take an admission
+    // permit, so that a password spray can't crowd out other users.
+    let app = app.layer(from_fn_with_state(
+        state.clone(),
+        middleware::rate_limit::auth_rate_limit_layer,
+    ));
+
+    // A read only replica refers writes to a writable server. Referrals are cheap, so this is
+    // checked before admission control to avoid holding a permit for them.
+    let app = app.layer(from_fn_with_state(
+        state.clone(),
+        middleware::read_only::read_only_replica_layer,
+    ));
+
+    // Retried writes are answered from the outcome of the first attempt. This is outside of
+    // admission control so that a replay doesn't wait for a permit.
+    let app = app.layer(from_fn_with_state(
+        state.clone(),
+        middleware::idempotency::idempotency_layer,
+    ));
+
+    app.route("/status", get(generic::status))
+        .route("/status/live", get(generic::status_live))
+        .route("/status/ready", get(generic::status_ready))
+        .route("/metrics", get(generic::metrics))
         // This must be the LAST middleware.
         // This is because the last middleware here is the first to be entered and the last
         // to be exited, and this middleware sets up ids' and other bits for for logging
@@ -309,38 +557,6 @@ pub async fn create_https_server(
         // this MUST be the last layer before with_state else the span never starts and everything breaks.
         .layer(trace_layer)
         .with_state(state)
-        // the connect_info bit here lets us pick up the remote address of the client
-        .into_make_service_with_connect_info::<ClientConnInfo>();
-
-    let addr = SocketAddr::from_str(&config.address).map_err(|err| {
-        error!(
-            "Failed to parse address ({:?}) from config: {:?}",
-            config.address, err
-        );
-    })?;
-
-    info!("Starting the web server...");
-
-    match maybe_tls_acceptor {
-        Some(tls_acceptor) => {
-            let listener = match TcpListener::bind(addr).await {
-                Ok(l) => l,
-                Err(err) => {
-                    error!(?err, "Failed to bind tcp listener");
-                    return Err(());
-                }
-            };
-            Ok(task::spawn(server_loop(
-                tls_acceptor,
-                listener,
-                app,
-                rx,
-                server_message_tx,
-                tls_acceptor_reload_rx,
-            )))
-        }
-        None => Ok(task::spawn(server_loop_plaintext(addr, app, rx))),
-    }
 }
 
 async fn server_loop(
@@ -350,6 +566,7 @@ async fn server_loop(
     mut rx: broadcast::Receiver<CoreAction>,
     server_message_tx: broadcast::Sender<CoreAction>,
     mut tls_acceptor_reload_rx: mpsc::Receiver<SslAcceptor>,
+    tls_not_after: Arc<AtomicU64>,
 ) {
     pin_mut!(listener);
 
@@ -378,6 +595,10 @@ async fn server_loop(
             }
             Some(mut new_tls_acceptor) = tls_acceptor_reload_rx.recv() => {
                 std::mem::swap(&mut tls_acceptor, &mut new_tls_acceptor);
+                tls_not_after.store(
+                    crypto::tls_acceptor_not_after(&tls_acceptor).unwrap_or_default(),
+                    Ordering::Relaxed,
+                );
                 info!("Reloaded http tls acceptor");
             }
         }
@@ -410,6 +631,30 @@ async fn server_loop_plaintext(
     info!("Stopped {}", super::TaskName::HttpsServer);
 }
 
+async fn metrics_server_loop(
+    addr: SocketAddr,
+    app: IntoMakeServiceWithConnectInfo<Router, ClientConnInfo>,
+    mut rx: broadcast::Receiver<CoreAction>,
+) {
+    let listener = axum_server::bind(addr).serve(app);
+
+    pin_mut!(listener);
+
+    loop {
+        tokio::select! {
+            Ok(action) = rx.recv() => {
+                match action {
+                    CoreAction::Shutdown =>
+                        break,
+                }
+            }
+            _ = &mut listener => {}
+        }
+    }
+
+    info!("Stopped {}", super::TaskName::MetricsServer);
+}
+
 /// This handles an individual connection.
 pub(crate) async fn handle_conn(
     acceptor: SslAcceptor,
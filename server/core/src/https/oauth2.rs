@@ -30,7 +30,7 @@ use kanidm_proto::oauth2::AuthorisationResponse;
 use kanidm_proto::oauth2::DeviceAuthorizationResponse;
 use kanidmd_lib::idm::oauth2::{
     AccessTokenIntrospectRequest, AccessTokenRequest, AuthorisationRequest, AuthoriseResponse,
-    ErrorResponse, Oauth2Error, TokenRevokeRequest,
+    ClientRegistrationRequest, ErrorResponse, Oauth2Error, TokenRevokeRequest,
 };
 use kanidmd_lib::prelude::f_eq;
 use kanidmd_lib::prelude::*;
@@ -41,7 +41,10 @@ use serde_with::{serde_as, StringWithSeparator};
 
 #[cfg(feature = "dev-oauth2-device-flow")]
 use uri::OAUTH2_AUTHORISE_DEVICE;
-use uri::{OAUTH2_TOKEN_ENDPOINT, OAUTH2_TOKEN_INTROSPECT_ENDPOINT, OAUTH2_TOKEN_REVOKE_ENDPOINT};
+use uri::{
+    OAUTH2_REGISTRATION_ENDPOINT, OAUTH2_TOKEN_ENDPOINT, OAUTH2_TOKEN_INTROSPECT_ENDPOINT,
+    OAUTH2_TOKEN_REVOKE_ENDPOINT,
+};
 
 // TODO: merge this into a value in WebError later
 pub struct HTTPOauth2Error(Oauth2Error);
@@ -54,14 +57,20 @@ impl IntoResponse for HTTPOauth2Error {
             (
                 StatusCode::UNAUTHORIZED,
                 [
-                    (WWW_AUTHENTICATE, "Bearer"),
+                    (WWW_AUTHENTICATE, "Bearer, DPoP algs=\"ES256\""),
                     (ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
                 ],
             )
                 .into_response()
         } else {
+            let error_description = match &error {
+                Oauth2Error::RedirectUriRejected(reason) => reason.message(),
+                _ => None,
+            };
+
             let err = ErrorResponse {
                 error: error.to_string(),
+                error_description,
                 ..Default::default()
             };
 
@@ -238,6 +247,8 @@ async fn oauth2_authorise(
             client_name,
             scopes,
             pii_scopes,
+            scope_descriptions,
+            claims,
             consent_token,
         }) => {
             // Render a redirect to the consent page for the user to interact with
@@ -248,6 +259,8 @@ async fn oauth2_authorise(
                 client_name,
                 scopes,
                 pii_scopes,
+                scope_descriptions,
+                claims,
                 consent_token,
             })
             .unwrap();
@@ -280,6 +293,24 @@ async fn oauth2_authorise(
                 .body(body)
                 .unwrap()
         }
+        Ok(AuthoriseResponse::Rejected(reject)) => {
+            // The client asked that the user not be prompted, so we tell it why we can't proceed.
+            let redirect_uri = reject.build_redirect_uri();
+
+            #[allow(clippy::unwrap_used)]
+            Response::builder()
+                .status(StatusCode::FOUND)
+                .header(
+                    LOCATION,
+                    HeaderValue::from_str(redirect_uri.as_str()).unwrap(),
+                )
+                .header(
+                    ACCESS_CONTROL_ALLOW_ORIGIN,
+                    HeaderValue::from_str(&redirect_uri.origin().ascii_serialization()).unwrap(),
+                )
+                .body(Body::empty())
+                .unwrap()
+        }
         Ok(AuthoriseResponse::AuthenticationRequired { .. })
         | Err(Oauth2Error::AuthenticationRequired) => {
             // This will trigger our ui to auth and retry.
@@ -291,8 +322,8 @@ async fn oauth2_authorise(
                 .body(Body::empty())
                 .unwrap()
         }
-        Err(Oauth2Error::AccessDenied) => {
-            // If scopes are not available for this account.
+        Err(Oauth2Error::AccessDenied) | Err(Oauth2Error::ClientNotAssigned) => {
+            // If scopes are not available for this account, or it is not assigned to the client.
             #[allow(clippy::expect_used)]
             Response::builder()
                 .status(StatusCode::FORBIDDEN)
@@ -310,6 +341,15 @@ async fn oauth2_authorise(
         // To further this, it appears that a malicious client configuration can set a phishing
         // site as the redirect URL, and then use that to trigger certain types of attacks. Instead
         // we do NOT redirect in an error condition, and just render the error ourselves.
+        Err(e @ Oauth2Error::RedirectUriRejected(_)) => {
+            admin_error!(
+                "Unable to authorise - Error ID: {:?} error: {:?}",
+                kopid.eventid,
+                e
+            );
+            // Describe why, so that the developer of the client can correct the redirect uri.
+            HTTPOauth2Error(e).into_response()
+        }
         Err(e) => {
             admin_error!(
                 "Unable to authorise - Error ID: {:?} error: {}",
@@ -513,6 +553,32 @@ pub async fn oauth2_token_post(
     }
 }
 
+/// Dynamic client registration as defined by rfc7591. The caller must provide a bearer
+/// token for an identity that is permitted to create OAuth2 clients.
+///
+/// Registration is performed by automation, not by browsers, so unlike the other OAuth2
+/// endpoints this does not allow cross origin requests.
+#[instrument(skip(state, kopid, client_auth_info, reg_req), level = "DEBUG")]
+pub async fn oauth2_register_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(reg_req): Json<ClientRegistrationRequest>,
+) -> Response {
+    match state
+        .qe_w_ref
+        .handle_oauth2_client_register(client_auth_info, reg_req, kopid.eventid)
+        .await
+    {
+        Ok(reg_res) => (StatusCode::CREATED, Json(reg_res)).into_response(),
+        Err(e) => {
+            let mut res = HTTPOauth2Error(e).into_response();
+            res.headers_mut().remove(ACCESS_CONTROL_ALLOW_ORIGIN);
+            res
+        }
+    }
+}
+
 // For future openid integration
 pub async fn oauth2_openid_discovery_get(
     State(state): State<ServerState>,
@@ -620,7 +686,13 @@ pub async fn oauth2_openid_userinfo_get(
 
     let res = state
         .qe_r_ref
-        .handle_oauth2_openid_userinfo(client_id, client_token, kopid.eventid)
+        .handle_oauth2_openid_userinfo(
+            client_id,
+            client_token,
+            client_auth_info.dpop,
+            client_auth_info.client_cert,
+            kopid.eventid,
+        )
         .await;
 
     match res {
@@ -876,6 +948,7 @@ pub fn route_setup(state: ServerState) -> Router<ServerState> {
             post(oauth2_token_introspect_post),
         )
         .route(OAUTH2_TOKEN_REVOKE_ENDPOINT, post(oauth2_token_revoke_post))
+        .route(OAUTH2_REGISTRATION_ENDPOINT, post(oauth2_register_post))
         .merge(openid_router)
         .with_state(state)
         .layer(from_fn(super::middleware::caching::dont_cache_me));
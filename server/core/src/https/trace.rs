@@ -3,8 +3,10 @@
 use axum::http::{Request, StatusCode};
 use kanidm_proto::constants::KOPID;
 use sketching::event_dynamic_lvl;
+use sketching::otel::TraceContextHeaders;
 use tower_http::LatencyUnit;
 use tracing::{Level, Span};
+use uuid::Uuid;
 
 /// The default way Spans will be created for Trace.
 ///
@@ -28,13 +30,33 @@ impl<B> tower_http::trace::MakeSpan<B> for DefaultMakeSpanKanidmd {
     fn make_span(&mut self, request: &Request<B>) -> Span {
         // Needs to be at info to ensure that there is always a span for each
         // tracing event to hook into.
-        tracing::span!(
+        let span = tracing::span!(
             Level::INFO,
             "request",
             method = %request.method(),
             uri = %request.uri(),
             version = ?request.version(),
-        )
+            trace_id = tracing::field::Empty,
+            kopid = tracing::field::Empty,
+        );
+
+        // If the caller is tracing this request, join our spans to their trace.
+        let headers = request.headers();
+        sketching::otel::continue_trace(
+            &span,
+            &TraceContextHeaders {
+                traceparent: headers.get("traceparent").and_then(|hv| hv.to_str().ok()),
+                tracestate: headers.get("tracestate").and_then(|hv| hv.to_str().ok()),
+            },
+        );
+
+        // When traces are exported, record the trace id so that the logs of a request can be
+        // matched to its trace.
+        if let Some(trace_id) = sketching::otel::trace_id(&span) {
+            span.record("trace_id", Uuid::from_bytes(trace_id).simple().to_string());
+        }
+
+        span
     }
 }
 
@@ -70,12 +92,14 @@ impl<B> tower_http::trace::OnResponse<B> for DefaultOnResponseKanidmd {
         self,
         response: &axum::response::Response<B>,
         latency: std::time::Duration,
-        _span: &Span,
+        span: &Span,
     ) {
         let kopid = match response.headers().get(KOPID) {
             Some(val) => val.to_str().unwrap_or("<invalid kopid>"),
             None => "<unknown>",
         };
+        // So that the trace of a request can be found from the operation id.
+        span.record("kopid", kopid);
         let (level, msg) =
             match response.status().is_success() || response.status().is_informational() {
                 true => (Level::DEBUG, "response sent"),
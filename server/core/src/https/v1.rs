@@ -1,7 +1,7 @@
 //! The V1 API things!
 
-use axum::extract::{Path, State};
-use axum::http::{HeaderMap, HeaderValue};
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::http::{header, HeaderMap, HeaderValue};
 use axum::middleware::from_fn;
 use axum::response::{IntoResponse, Response};
 use axum::routing::{delete, get, post, put};
@@ -13,16 +13,24 @@ use std::net::IpAddr;
 use uuid::Uuid;
 
 use kanidm_proto::internal::{
-    ApiToken, AppLink, CUIntentToken, CURequest, CUSessionToken, CUStatus, CreateRequest,
-    CredentialStatus, DeleteRequest, IdentifyUserRequest, IdentifyUserResponse, ModifyRequest,
-    RadiusAuthToken, SearchRequest, SearchResponse, UserAuthToken, COOKIE_AUTH_SESSION_ID,
-    COOKIE_BEARER_TOKEN,
+    AccessControlPreview, AccessControlPreviewQuery, ApiToken, AppLink, ApplicationPassword,
+    ApplicationPasswordRequest, BulkImportRequest, BulkImportResponse, CUIntentToken,
+    CUIntentTokenInfo, CUIntentTokenRequest, CUPasswordCheck, CURequest, CUSessionToken, CUStatus,
+    CreateRequest, CredentialSoftLockStatus, CredentialStatus, DeleteRequest, DryRunQuery,
+    DryRunResponse, EffectiveAccountPolicy, EntryHistoryResponse, EntryInspectResponse,
+    IdentifyUserRequest, IdentifyUserResponse, ModifyRequest, RadiusAuthToken, SearchRequest,
+    SearchResponse, UserAuthToken, COOKIE_AUTH_SESSION_ID, COOKIE_BEARER_TOKEN,
 };
 use kanidm_proto::v1::{
     AccountUnixExtend, ApiTokenGenerate, AuthIssueSession, AuthRequest, AuthResponse,
-    AuthState as ProtoAuthState, Entry as ProtoEntry, GroupUnixExtend, SingleStringRequest,
-    UatStatus, UnixGroupToken, UnixUserToken, WhoamiResponse,
+    AuthState as ProtoAuthState, AuthStep, BreakGlassChallenge, BreakGlassCodeRequest,
+    BreakGlassRequest, BreakGlassResponse, ChangesQuery, ChangesResponse, Entry as ProtoEntry,
+    EntryApplyResponse, GroupMembershipRequestApprove, GroupMembershipRequestCreate,
+    GroupMembershipRequests, GroupTemporaryMembers, GroupUnixExtend, JwtPublicKeyAdd,
+    Oauth2SessionStatus, SingleStringRequest, UatStatus, UnixGroupToken, UnixUserToken,
+    WhoamiResponse,
 };
+use kanidmd_lib::idm::audit::AuditRecord;
 use kanidmd_lib::idm::event::AuthResult;
 use kanidmd_lib::idm::AuthState;
 use kanidmd_lib::prelude::*;
@@ -30,16 +38,30 @@ use kanidmd_lib::value::PartialValue;
 
 use super::errors::WebError;
 use super::middleware::caching::{cache_me_short, dont_cache_me};
+use super::middleware::rate_limit::{check_account_rate_limit, rate_limited_response};
 use super::middleware::KOpId;
 use super::ServerState;
+use crate::audit::AuditQuery;
 use crate::https::apidocs::response_schema::{ApiResponseWithout200, DefaultApiResponse};
 use crate::https::extractors::{TrustedClientIp, VerifiedClientInformation};
 
+/// The limit on the size of a batch of a bulk import.
+const DEFAULT_BULK_IMPORT_BYTES: usize = 1024 * 1024 * 8;
+/// How many accounts and entries are sampled for an access control preview by default.
+const ACCESS_CONTROL_PREVIEW_SAMPLE_DEFAULT: usize = 50;
+/// The limit on the sample size of an access control preview, as each account is checked
+/// against each entry.
+const ACCESS_CONTROL_PREVIEW_SAMPLE_MAX: usize = 500;
+
 #[utoipa::path(
     post,
     path = "/v1/raw/create",
+    params(
+        ("dry_run" = Option<bool>, Query, description = "Check the create without committing it"),
+    ),
     responses(
-        DefaultApiResponse,
+        (status=200, body=Option<DryRunResponse>, content_type="application/json"),
+        ApiResponseWithout200,
     ),
     request_body=CreateRequest,
     security(("token_jwt" = [])),
@@ -51,21 +73,35 @@ pub async fn raw_create(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<DryRunQuery>,
     Json(msg): Json<CreateRequest>,
-) -> Result<Json<()>, WebError> {
+) -> Result<Response, WebError> {
+    if query.dry_run {
+        return state
+            .qe_w_ref
+            .handle_create_dry_run(client_auth_info, msg, kopid.eventid)
+            .await
+            .map(|response| Json::from(response).into_response())
+            .map_err(WebError::from);
+    }
+
     state
         .qe_w_ref
         .handle_create(client_auth_info, msg, kopid.eventid)
         .await
-        .map(Json::from)
+        .map(|()| Json::from(()).into_response())
         .map_err(WebError::from)
 }
 
 #[utoipa::path(
     post,
     path = "/v1/raw/modify",
+    params(
+        ("dry_run" = Option<bool>, Query, description = "Check the modify without committing it"),
+    ),
     responses(
-        DefaultApiResponse,
+        (status=200, body=Option<DryRunResponse>, content_type="application/json"),
+        ApiResponseWithout200,
     ),
     request_body=ModifyRequest,
     security(("token_jwt" = [])),
@@ -77,21 +113,35 @@ pub async fn raw_modify(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<DryRunQuery>,
     Json(msg): Json<ModifyRequest>,
-) -> Result<Json<()>, WebError> {
+) -> Result<Response, WebError> {
+    if query.dry_run {
+        return state
+            .qe_w_ref
+            .handle_modify_dry_run(client_auth_info, msg, kopid.eventid)
+            .await
+            .map(|response| Json::from(response).into_response())
+            .map_err(WebError::from);
+    }
+
     state
         .qe_w_ref
         .handle_modify(client_auth_info, msg, kopid.eventid)
         .await
-        .map(Json::from)
+        .map(|()| Json::from(()).into_response())
         .map_err(WebError::from)
 }
 
 #[utoipa::path(
     post,
     path = "/v1/raw/delete",
+    params(
+        ("dry_run" = Option<bool>, Query, description = "Check the delete without committing it"),
+    ),
     responses(
-        DefaultApiResponse,
+        (status=200, body=Option<DryRunResponse>, content_type="application/json"),
+        ApiResponseWithout200,
     ),
     request_body=DeleteRequest,
     security(("token_jwt" = [])),
@@ -103,13 +153,23 @@ pub async fn raw_delete(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<DryRunQuery>,
     Json(msg): Json<DeleteRequest>,
-) -> Result<Json<()>, WebError> {
+) -> Result<Response, WebError> {
+    if query.dry_run {
+        return state
+            .qe_w_ref
+            .handle_delete_dry_run(client_auth_info, msg, kopid.eventid)
+            .await
+            .map(|response| Json::from(response).into_response())
+            .map_err(WebError::from);
+    }
+
     state
         .qe_w_ref
         .handle_delete(client_auth_info, msg, kopid.eventid)
         .await
-        .map(Json::from)
+        .map(|()| Json::from(()).into_response())
         .map_err(WebError::from)
 }
 
@@ -140,6 +200,34 @@ pub async fn raw_search(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/import",
+    responses(
+        (status = 200, body=BulkImportResponse, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    request_body=BulkImportRequest,
+    security(("token_jwt" = [])),
+    tag = "v1/import",
+    operation_id="bulk_import"
+)]
+/// Import a batch of entries. Entries that could not be imported are reported in the
+/// response, rather than failing the request.
+pub async fn bulk_import(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(msg): Json<BulkImportRequest>,
+) -> Result<Json<BulkImportResponse>, WebError> {
+    state
+        .qe_w_ref
+        .handle_bulk_import(client_auth_info, msg, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/self",
@@ -288,6 +376,120 @@ pub async fn json_rest_event_delete_id(
         .map_err(WebError::from)
 }
 
+/// The version given in an If-Match header, without the quotes of the entity tag.
+fn if_match_version(headers: &HeaderMap) -> Result<Option<String>, WebError> {
+    headers
+        .get(header::IF_MATCH)
+        .map(|value| {
+            value
+                .to_str()
+                .map(|value| {
+                    value
+                        .trim()
+                        .trim_start_matches("W/")
+                        .trim_matches('"')
+                        .to_string()
+                })
+                .map_err(|_| WebError::from(OperationError::InvalidRequestState))
+        })
+        .transpose()
+}
+
+/// Add the version of an entry as the ETag of a response.
+fn with_etag(mut response: Response, version: Option<&str>) -> Response {
+    if let Some(version) = version {
+        match HeaderValue::from_str(&format!("\"{}\"", version)) {
+            Ok(value) => {
+                response.headers_mut().insert(header::ETAG, value);
+            }
+            Err(err) => {
+                error!(?err, "Failed to add version {} to etag header", version);
+            }
+        }
+    }
+    response
+}
+
+/// Common event handler to retrieve an entry with a name or id, along with its version in
+/// the ETag header.
+pub async fn json_rest_event_get_id_versioned(
+    state: ServerState,
+    id: String,
+    filter: Filter<FilterInvalid>,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+) -> Result<Response, WebError> {
+    let filter = Filter::join_parts_and(filter, filter_all!(f_id(id.as_str())));
+
+    let (entry, version) = state
+        .qe_r_ref
+        .handle_internalsearch_versioned(client_auth_info, filter, kopid.eventid)
+        .await?
+        .unzip();
+
+    Ok(with_etag(
+        Json::from(entry).into_response(),
+        version.as_deref(),
+    ))
+}
+
+/// Common event handler to create an entry with a name or id, or update it to match the
+/// attributes that are given. If an If-Match header is present, the entry must exist and be
+/// unchanged since that version.
+pub async fn json_rest_event_put_id(
+    state: ServerState,
+    id: String,
+    filter: Filter<FilterInvalid>,
+    obj: ProtoEntry,
+    headers: HeaderMap,
+    query: DryRunQuery,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+) -> Result<Response, WebError> {
+    let if_match = if_match_version(&headers)?;
+
+    let response = state
+        .qe_w_ref
+        .handle_entry_apply(
+            client_auth_info,
+            id,
+            filter,
+            obj,
+            if_match,
+            query.dry_run,
+            kopid.eventid,
+        )
+        .await?;
+
+    let version = response.version.clone();
+    Ok(with_etag(
+        Json::from(response).into_response(),
+        version.as_deref(),
+    ))
+}
+
+/// Common event handler to delete an entry with a name or id. If an If-Match header is
+/// present, the entry is only deleted if it is unchanged since that version.
+pub async fn json_rest_event_delete_id_versioned(
+    state: ServerState,
+    id: String,
+    filter: Filter<FilterInvalid>,
+    headers: HeaderMap,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+) -> Result<Json<()>, WebError> {
+    let Some(if_match) = if_match_version(&headers)? else {
+        return json_rest_event_delete_id(state, id, filter, kopid, client_auth_info).await;
+    };
+
+    state
+        .qe_w_ref
+        .handle_entry_delete_if_match(client_auth_info, id, filter, if_match, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 pub async fn json_rest_event_get_attr(
     state: ServerState,
     id: &str,
@@ -626,6 +828,33 @@ pub async fn person_post(
     json_rest_event_post(state, classes, obj, kopid, client_auth_info).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/person/_contractor",
+    responses(
+        DefaultApiResponse,
+    ),
+    request_body=ProtoEntry,
+    security(("token_jwt" = [])),
+    tag = "v1/person",
+    operation_id = "person_contractor_post",
+)]
+/// Expects the following fields in the attrs field of the req: [name, displayname, sponsor, account_expire]
+pub async fn person_contractor_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<ProtoEntry>,
+) -> Result<Json<()>, WebError> {
+    let classes: Vec<String> = vec![
+        EntryClass::Contractor.into(),
+        EntryClass::Person.into(),
+        EntryClass::Account.into(),
+        EntryClass::Object.into(),
+    ];
+    json_rest_event_post(state, classes, obj, kopid, client_auth_info).await
+}
+
 #[utoipa::path(
     get,
     path = "/v1/person/_search/{id}",
@@ -666,9 +895,57 @@ pub async fn person_id_get(
     Path(id): Path<String>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
-) -> Result<Json<Option<ProtoEntry>>, WebError> {
+) -> Result<Response, WebError> {
     let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Person.into()));
-    json_rest_event_get_id(state, id, filter, None, kopid, client_auth_info).await
+    json_rest_event_get_id_versioned(state, id, filter, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/person/{id}",
+    params(
+        ("dry_run" = Option<bool>, Query, description = "Report what would change without changing it"),
+    ),
+    responses(
+        (status=200, body=EntryApplyResponse, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    request_body=ProtoEntry,
+    security(("token_jwt" = [])),
+    tag = "v1/person",
+    operation_id = "person_id_put",
+)]
+/// Create the person if it doesn't exist, or update it to match the attributes that are given.
+pub async fn person_id_put(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<DryRunQuery>,
+    headers: HeaderMap,
+    Json(mut obj): Json<ProtoEntry>,
+) -> Result<Response, WebError> {
+    // Classes are only set when the entry is created.
+    obj.attrs.insert(
+        Attribute::Class.to_string(),
+        vec![
+            EntryClass::Person.into(),
+            EntryClass::Account.into(),
+            EntryClass::Object.into(),
+        ],
+    );
+    let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Person.into()));
+    json_rest_event_put_id(
+        state,
+        id,
+        filter,
+        obj,
+        headers,
+        query,
+        kopid,
+        client_auth_info,
+    )
+    .await
 }
 
 #[utoipa::path(
@@ -686,9 +963,10 @@ pub async fn person_id_delete(
     Path(id): Path<String>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    headers: HeaderMap,
 ) -> Result<Json<()>, WebError> {
     let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Person.into()));
-    json_rest_event_delete_id(state, id, filter, kopid, client_auth_info).await
+    json_rest_event_delete_id_versioned(state, id, filter, headers, kopid, client_auth_info).await
 }
 
 // == person -> certificates
@@ -836,9 +1114,57 @@ pub async fn service_account_id_get(
     Path(id): Path<String>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
-) -> Result<Json<Option<ProtoEntry>>, WebError> {
+) -> Result<Response, WebError> {
+    let filter = filter_all!(f_eq(Attribute::Class, EntryClass::ServiceAccount.into()));
+    json_rest_event_get_id_versioned(state, id, filter, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/service_account/{id}",
+    params(
+        ("dry_run" = Option<bool>, Query, description = "Report what would change without changing it"),
+    ),
+    responses(
+        (status=200, body=EntryApplyResponse, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    request_body=ProtoEntry,
+    security(("token_jwt" = [])),
+    tag = "v1/service_account",
+    operation_id = "service_account_id_put",
+)]
+/// Create the service account if it doesn't exist, or update it to match the attributes that are given.
+pub async fn service_account_id_put(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<DryRunQuery>,
+    headers: HeaderMap,
+    Json(mut obj): Json<ProtoEntry>,
+) -> Result<Response, WebError> {
+    // Classes are only set when the entry is created.
+    obj.attrs.insert(
+        Attribute::Class.to_string(),
+        vec![
+            EntryClass::ServiceAccount.into(),
+            EntryClass::Account.into(),
+            EntryClass::Object.into(),
+        ],
+    );
     let filter = filter_all!(f_eq(Attribute::Class, EntryClass::ServiceAccount.into()));
-    json_rest_event_get_id(state, id, filter, None, kopid, client_auth_info).await
+    json_rest_event_put_id(
+        state,
+        id,
+        filter,
+        obj,
+        headers,
+        query,
+        kopid,
+        client_auth_info,
+    )
+    .await
 }
 
 #[utoipa::path(
@@ -855,9 +1181,10 @@ pub async fn service_account_id_delete(
     Path(id): Path<String>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    headers: HeaderMap,
 ) -> Result<Json<()>, WebError> {
     let filter = filter_all!(f_eq(Attribute::Class, EntryClass::ServiceAccount.into()));
-    json_rest_event_delete_id(state, id, filter, kopid, client_auth_info).await
+    json_rest_event_delete_id_versioned(state, id, filter, headers, kopid, client_auth_info).await
 }
 
 #[utoipa::path(
@@ -959,14 +1286,7 @@ pub async fn service_account_api_token_post(
 ) -> Result<Json<String>, WebError> {
     state
         .qe_w_ref
-        .handle_service_account_api_token_generate(
-            client_auth_info,
-            id,
-            obj.label,
-            obj.expiry,
-            obj.read_write,
-            kopid.eventid,
-        )
+        .handle_service_account_api_token_generate(client_auth_info, id, obj, kopid.eventid)
         .await
         .map(Json::from)
         .map_err(WebError::from)
@@ -996,6 +1316,58 @@ pub async fn service_account_api_token_delete(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/service_account/{id}/_jwt_public_key",
+    request_body = JwtPublicKeyAdd,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/service_account",
+    operation_id = "service_account_jwt_public_key_post",
+)]
+/// Register a public key that may sign jwt assertions to authenticate the service account.
+/// The keys that are registered are listed by their label in the `jwt_public_key` attribute.
+pub async fn service_account_jwt_public_key_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(id): Path<String>,
+    Json(obj): Json<JwtPublicKeyAdd>,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_w_ref
+        .handle_service_account_jwt_public_key_add(client_auth_info, id, obj, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/service_account/{id}/_jwt_public_key/{label}",
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/service_account",
+    operation_id = "service_account_jwt_public_key_delete",
+)]
+pub async fn service_account_jwt_public_key_delete(
+    State(state): State<ServerState>,
+    Path((id, label)): Path<(String, String)>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_w_ref
+        .handle_service_account_jwt_public_key_remove(client_auth_info, id, label, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/person/{id}/_attr/{attr}",
@@ -1245,6 +1617,8 @@ pub async fn person_id_credential_update_intent_ttl_get(
             client_auth_info,
             id,
             Some(Duration::from_secs(ttl)),
+            None,
+            false,
             kopid.eventid,
         )
         .await
@@ -1271,31 +1645,43 @@ pub async fn person_id_credential_update_intent_get(
 ) -> Result<Json<CUIntentToken>, WebError> {
     state
         .qe_w_ref
-        .handle_idmcredentialupdateintent(client_auth_info, id, None, kopid.eventid)
+        .handle_idmcredentialupdateintent(client_auth_info, id, None, None, false, kopid.eventid)
         .await
         .map(Json::from)
         .map_err(WebError::from)
 }
 
 #[utoipa::path(
-    get,
-    path = "/v1/account/{id}/_user_auth_token",
+    post,
+    path = "/v1/person/{id}/_credential/_update_intent",
+    request_body=CUIntentTokenRequest,
     responses(
-        (status=200), // TODO: define response
+        (status=200, body=CUIntentToken, content_type="application/json"),
         ApiResponseWithout200,
     ),
     security(("token_jwt" = [])),
-    tag = "v1/account",
+    tag = "v1/person/credential",
 )]
-pub async fn account_id_user_auth_token_get(
+/// Create a credential reset token for the person, with an optional validity period in
+/// seconds and an optional number of times the token may be used.
+#[instrument(level = "trace", skip(state, kopid))]
+pub async fn person_id_credential_update_intent_post(
     State(state): State<ServerState>,
-    Path(id): Path<String>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
-) -> Result<Json<Vec<UatStatus>>, WebError> {
+    Path(id): Path<String>,
+    Json(request): Json<CUIntentTokenRequest>,
+) -> Result<Json<CUIntentToken>, WebError> {
     state
-        .qe_r_ref
-        .handle_account_user_auth_token_get(client_auth_info, id, kopid.eventid)
+        .qe_w_ref
+        .handle_idmcredentialupdateintent(
+            client_auth_info,
+            id,
+            request.ttl.map(Duration::from_secs),
+            request.max_uses,
+            request.send_mail,
+            kopid.eventid,
+        )
         .await
         .map(Json::from)
         .map_err(WebError::from)
@@ -1303,9 +1689,136 @@ pub async fn account_id_user_auth_token_get(
 
 #[utoipa::path(
     get,
-    path = "/v1/account/{id}/_user_auth_token/{token_id}",
+    path = "/v1/person/{id}/_credential/_intents",
     responses(
-        DefaultApiResponse,
+        (status=200, body=Vec<CUIntentTokenInfo>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/person/credential",
+)]
+/// List the credential reset tokens of the person that have not yet expired.
+#[instrument(level = "trace", skip(state, kopid))]
+pub async fn person_id_credential_intents_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<CUIntentTokenInfo>>, WebError> {
+    state
+        .qe_w_ref
+        .handle_idmcredentialupdateintent_list(client_auth_info, id, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/person/{id}/_credential/_intents/{token_ref}",
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/person/credential",
+)]
+/// Revoke a credential reset token of the person, so that it can no longer be used.
+#[instrument(level = "trace", skip(state, kopid))]
+pub async fn person_id_credential_intents_delete(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path((id, token_ref)): Path<(String, String)>,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_w_ref
+        .handle_idmcredentialupdateintent_revoke(client_auth_info, id, token_ref, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/account/{id}/_user_auth_token",
+    responses(
+        (status=200), // TODO: define response
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/account",
+)]
+pub async fn account_id_user_auth_token_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<UatStatus>>, WebError> {
+    state
+        .qe_r_ref
+        .handle_account_user_auth_token_get(client_auth_info, id, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/account/{id}/_oauth2_session",
+    responses(
+        (status=200, body=Vec<Oauth2SessionStatus>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/account",
+)]
+/// List the OAuth2 sessions that have been issued to an account, including why the server
+/// revoked a session.
+pub async fn account_id_oauth2_session_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<Oauth2SessionStatus>>, WebError> {
+    state
+        .qe_r_ref
+        .handle_oauth2_session_get(client_auth_info, id, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/account/{id}/_account_policy",
+    responses(
+        (status=200, body=EffectiveAccountPolicy, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/account",
+)]
+/// The account policy that applies to an account, merged from the policies of the groups
+/// that it is a member of.
+pub async fn account_id_account_policy_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<EffectiveAccountPolicy>, WebError> {
+    state
+        .qe_r_ref
+        .handle_account_policy_read(client_auth_info, id, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/account/{id}/_user_auth_token/{token_id}",
+    responses(
+        DefaultApiResponse,
     ),
     security(("token_jwt" = [])),
     tag = "v1/account",
@@ -1349,6 +1862,33 @@ pub async fn credential_update_exchange_intent(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/credential/_exchange_expired",
+    request_body=String,
+    responses(
+        (status=200), // TODO: define response
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/credential",
+)]
+/// Exchange the token returned when authenticating with an expired password for a
+/// credential update session, so that the password can be changed.
+pub async fn credential_update_exchange_expired(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(token): Json<String>,
+) -> Result<Json<(CUSessionToken, CUStatus)>, WebError> {
+    state
+        .qe_w_ref
+        .handle_password_expired_exchange(client_auth_info, token, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     post,
     path = "/v1/credential/_status",
@@ -1372,6 +1912,56 @@ pub async fn credential_update_status(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/credential/_passphrase",
+    request_body=CUSessionToken,
+    responses(
+        (status=200, body=String, description="A passphrase that satisfies the password policy of the account"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/credential",
+)]
+/// Suggest a passphrase for the account in this credential update session.
+pub async fn credential_update_suggest_passphrase(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    Json(session_token): Json<CUSessionToken>,
+) -> Result<Json<String>, WebError> {
+    state
+        .qe_r_ref
+        .handle_idmcredentialsuggestpassphrase(session_token, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/credential/_check_password",
+    responses(
+        (status=200, body=CUPasswordCheck, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/credential",
+)]
+/// Check a candidate password against the password policy of the account in this credential
+/// update session, without setting it. Checks are rate limited per session.
+pub async fn credential_update_check_password(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    Json((pw, session_token)): Json<(String, CUSessionToken)>,
+) -> Result<Json<CUPasswordCheck>, WebError> {
+    state
+        .qe_r_ref
+        .handle_idmcredentialcheckpassword(session_token, pw, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     post,
     path = "/v1/credential/_update",
@@ -1487,7 +2077,10 @@ pub async fn service_account_id_credential_status_get(
         Err(err) => {
             if let OperationError::NoMatchingAttributes = err {
                 debug!("No credentials set on account {}, returning empty list", id);
-                Ok(Json(CredentialStatus { creds: Vec::new() }))
+                Ok(Json(CredentialStatus {
+                    creds: Vec::new(),
+                    usage: Vec::new(),
+                }))
             } else {
                 Err(WebError::from(err))
             }
@@ -1521,7 +2114,10 @@ pub async fn person_get_id_credential_status(
         Err(err) => {
             if let OperationError::NoMatchingAttributes = err {
                 debug!("No credentials set on person {}, returning empty list", id);
-                Ok(Json(CredentialStatus { creds: Vec::new() }))
+                Ok(Json(CredentialStatus {
+                    creds: Vec::new(),
+                    usage: Vec::new(),
+                }))
             } else {
                 Err(WebError::from(err))
             }
@@ -1529,6 +2125,56 @@ pub async fn person_get_id_credential_status(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/person/{id}/_credential/_softlock",
+    responses(
+        (status=200, body=CredentialSoftLockStatus, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/person/credential",
+)]
+/// Show the softlock of a person's primary credential on this server.
+pub async fn person_get_id_credential_softlock(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(id): Path<String>,
+) -> Result<Json<CredentialSoftLockStatus>, WebError> {
+    state
+        .qe_r_ref
+        .handle_idmcredentialsoftlockstatus(client_auth_info, id, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/person/{id}/_credential/_softlock",
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/person/credential",
+)]
+/// Clear the softlock of a person's primary credential on this server, allowing it to be
+/// used immediately.
+pub async fn person_delete_id_credential_softlock(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(id): Path<String>,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_r_ref
+        .handle_idmcredentialsoftlockclear(client_auth_info, id, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/person/{id}/_ssh_pubkeys",
@@ -1882,6 +2528,60 @@ pub async fn person_id_radius_delete(
     json_rest_event_delete_id_attr(state, id, attr, filter, None, kopid, client_auth_info).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/person/{id}/_radius/_certificate",
+    request_body=String,
+    responses(
+        (status=200, body=String, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/person/radius",
+    operation_id = "person_id_radius_certificate_post"
+)]
+/// Sign a PEM encoded certificate signing request, issuing a client certificate for EAP-TLS.
+/// Returns the PEM encoded certificate.
+pub async fn person_id_radius_certificate_post(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(csr): Json<String>,
+) -> Result<Json<String>, WebError> {
+    state
+        .qe_w_ref
+        .handle_radiuscertificateissue(client_auth_info, id, csr, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/radius/_ca_certificate",
+    responses(
+        (status=200, body=String, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/radius",
+    operation_id = "radius_ca_certificate_get"
+)]
+/// The PEM encoded certificate authority that issues client certificates for EAP-TLS.
+pub async fn radius_ca_certificate_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<String>, WebError> {
+    state
+        .qe_w_ref
+        .handle_radiuscacertificate(client_auth_info, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/person/{id}/_radius/_token",
@@ -2187,28 +2887,57 @@ pub async fn person_identify_user_post(
 }
 
 #[utoipa::path(
-    get,
-    path = "/v1/group",
+    post,
+    path = "/v1/person/{id}/_impersonate",
+    request_body = Option<u64>,
     responses(
-        (status=200,body=Vec<ProtoEntry>, content_type="application/json"),
+        (status=200, body=String, content_type="application/json"),
         ApiResponseWithout200,
     ),
     security(("token_jwt" = [])),
-    tag = "v1/group",
-    operation_id = "group_get",
+    tag = "v1/person",
+    operation_id = "person_id_impersonate_post"
 )]
-/// Returns all groups visible  to the user
-pub async fn group_get(
+/// Issue a read only session that acts as this person, for members of idm_impersonation_admins.
+/// The body is the number of seconds the session should last.
+pub async fn person_id_impersonate_post(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
-) -> Result<Json<Vec<ProtoEntry>>, WebError> {
-    let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()));
-    json_rest_event_get(state, None, filter, kopid, client_auth_info).await
-}
-
-#[utoipa::path(
-    get,
+    Path(id): Path<String>,
+    Json(expiry): Json<Option<u64>>,
+) -> Result<Json<String>, WebError> {
+    state
+        .qe_w_ref
+        .handle_impersonate_session(client_auth_info, id, expiry, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/group",
+    responses(
+        (status=200,body=Vec<ProtoEntry>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/group",
+    operation_id = "group_get",
+)]
+/// Returns all groups visible  to the user
+pub async fn group_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<ProtoEntry>>, WebError> {
+    let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()));
+    json_rest_event_get(state, None, filter, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    get,
     path = "/v1/group/_search/{id}",
     responses(
         (status=200, body=Option<ProtoEntry>, content_type="application/json"),
@@ -2267,9 +2996,53 @@ pub async fn group_id_get(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Path(id): Path<String>,
-) -> Result<Json<Option<ProtoEntry>>, WebError> {
+) -> Result<Response, WebError> {
+    let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()));
+    json_rest_event_get_id_versioned(state, id, filter, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/group/{id}",
+    params(
+        ("dry_run" = Option<bool>, Query, description = "Report what would change without changing it"),
+    ),
+    responses(
+        (status=200, body=EntryApplyResponse, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    request_body=ProtoEntry,
+    security(("token_jwt" = [])),
+    tag = "v1/group",
+    operation_id = "group_id_put",
+)]
+/// Create the group if it doesn't exist, or update it to match the attributes that are given.
+pub async fn group_id_put(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(id): Path<String>,
+    Query(query): Query<DryRunQuery>,
+    headers: HeaderMap,
+    Json(mut obj): Json<ProtoEntry>,
+) -> Result<Response, WebError> {
+    // Classes are only set when the entry is created.
+    obj.attrs.insert(
+        Attribute::Class.to_string(),
+        vec![EntryClass::Group.into(), EntryClass::Object.into()],
+    );
     let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()));
-    json_rest_event_get_id(state, id, filter, None, kopid, client_auth_info).await
+    json_rest_event_put_id(
+        state,
+        id,
+        filter,
+        obj,
+        headers,
+        query,
+        kopid,
+        client_auth_info,
+    )
+    .await
 }
 
 #[utoipa::path(
@@ -2316,9 +3089,10 @@ pub async fn group_id_delete(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Path(id): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Json<()>, WebError> {
     let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()));
-    json_rest_event_delete_id(state, id, filter, kopid, client_auth_info).await
+    json_rest_event_delete_id_versioned(state, id, filter, headers, kopid, client_auth_info).await
 }
 
 #[utoipa::path(
@@ -2435,6 +3209,140 @@ pub async fn group_id_unix_post(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/group/{id}/_temporary_members",
+    request_body = GroupTemporaryMembers,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/group",
+    operation_id = "group_id_temporary_members_post",
+)]
+/// Add members to a group until a point in time, after which their membership lapses.
+pub async fn group_id_temporary_members_post(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<GroupTemporaryMembers>,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_w_ref
+        .handle_group_add_temporary_members(client_auth_info, id, obj, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/group/{id}/_membership_request",
+    request_body = GroupMembershipRequestCreate,
+    responses(
+        (status=200, body=Uuid, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/group",
+    operation_id = "group_id_membership_request_post",
+)]
+/// Request that the authenticated account joins a group. The request is held until an entry
+/// manager of the group approves or denies it, and the id of the request is returned.
+pub async fn group_id_membership_request_post(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<GroupMembershipRequestCreate>,
+) -> Result<Json<Uuid>, WebError> {
+    state
+        .qe_w_ref
+        .handle_group_membership_request(client_auth_info, id, obj, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/group_membership_request",
+    responses(
+        (status=200, body=GroupMembershipRequests, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/group",
+    operation_id = "group_membership_request_get",
+)]
+/// List the requests the authenticated account has made to join groups, and the pending
+/// requests that it is able to approve.
+pub async fn group_membership_request_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<GroupMembershipRequests>, WebError> {
+    state
+        .qe_r_ref
+        .handle_list_group_membership_requests(client_auth_info, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/group_membership_request/{id}/_approve",
+    request_body = GroupMembershipRequestApprove,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/group",
+    operation_id = "group_membership_request_id_approve_post",
+)]
+/// Approve a request to join a group, adding the requester as a member.
+pub async fn group_membership_request_id_approve_post(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<GroupMembershipRequestApprove>,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_w_ref
+        .handle_group_membership_request_decide(client_auth_info, id, Some(obj), kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/group_membership_request/{id}/_deny",
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/group",
+    operation_id = "group_membership_request_id_deny_post",
+)]
+/// Deny a request to join a group.
+pub async fn group_membership_request_id_deny_post(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_w_ref
+        .handle_group_membership_request_decide(client_auth_info, id, None, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/group/{id}/_unix/_token",
@@ -2595,6 +3503,61 @@ pub async fn system_get(
     json_rest_event_get(state, None, filter, kopid, client_auth_info).await
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/system/_migrations",
+    responses(
+        (status=200,body=Vec<ProtoEntry>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/system",
+    operation_id = "system_migrations_get",
+)]
+/// Lists the records of the migrations that have been applied to the database.
+pub async fn system_migrations_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<ProtoEntry>>, WebError> {
+    let filter = filter_all!(f_eq(Attribute::Class, EntryClass::MigrationRecord.into()));
+    json_rest_event_get(state, None, filter, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/system/_access_control_preview",
+    params(
+        ("sample" = Option<usize>, Query, description = "How many accounts and entries to sample"),
+    ),
+    responses(
+        (status=200, body=AccessControlPreview, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/system",
+    operation_id = "system_access_control_preview_get",
+)]
+/// Compare the effective permissions of a sample of accounts between the enforced access
+/// controls and the staged ones.
+pub async fn system_access_control_preview_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<AccessControlPreviewQuery>,
+) -> Result<Json<AccessControlPreview>, WebError> {
+    let sample_size = query
+        .sample
+        .unwrap_or(ACCESS_CONTROL_PREVIEW_SAMPLE_DEFAULT)
+        .min(ACCESS_CONTROL_PREVIEW_SAMPLE_MAX);
+    state
+        .qe_r_ref
+        .handle_access_control_preview(client_auth_info, sample_size, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/system/_attr/{attr}",
@@ -2736,62 +3699,257 @@ pub async fn recycle_bin_get(
     let filter = filter_all!(f_pres(Attribute::Class));
     let attrs = None;
     state
-        .qe_r_ref
-        .handle_internalsearchrecycled(client_auth_info, filter, attrs, kopid.eventid)
+        .qe_r_ref
+        .handle_internalsearchrecycled(client_auth_info, filter, attrs, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/recycle_bin/{id}",
+    responses(
+        (status=200, body=Option<ProtoEntry>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/recycle_bin",
+    operation_id = "recycle_bin_id_get",
+)]
+pub async fn recycle_bin_id_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Option<ProtoEntry>>, WebError> {
+    let filter = filter_all!(f_id(id.as_str()));
+    let attrs = None;
+
+    state
+        .qe_r_ref
+        .handle_internalsearchrecycled(client_auth_info, filter, attrs, kopid.eventid)
+        .await
+        .map(|mut r| r.pop())
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/recycle_bin/{id}/_revive",
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/recycle_bin",
+    operation_id = "recycle_bin_revive_id_post",
+)]
+pub async fn recycle_bin_revive_id_post(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    let filter = filter_all!(f_id(id.as_str()));
+    state
+        .qe_w_ref
+        .handle_reviverecycled(client_auth_info, filter, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/audit",
+    params(
+        ("since" = Option<String>, Query, description = "RFC3339 time of the oldest event to return"),
+        ("until" = Option<String>, Query, description = "RFC3339 time of the newest event to return"),
+        ("event" = Option<String>, Query, description = "Only return events of this type"),
+        ("limit" = Option<usize>, Query, description = "The maximum number of events to return"),
+    ),
+    responses(
+        (status=200, body=String, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/audit",
+    operation_id = "audit_get",
+)]
+/// Query the audit events held in the hot tier. Events that have been exported to
+/// the cold tier are not returned.
+pub async fn audit_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditRecord>>, WebError> {
+    state
+        .qe_r_ref
+        .handle_audit_query(client_auth_info, query, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/self/_applinks",
+    responses(
+        (status=200, body=Vec<AppLink>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/self",
+    operation_id = "self_applinks_get",
+)]
+/// Returns your OAuth2 app links for the Web UI
+pub async fn applinks_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<AppLink>>, WebError> {
+    state
+        .qe_r_ref
+        .handle_list_applinks(client_auth_info, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/self/_ssh_certificate",
+    request_body=String,
+    responses(
+        (status=200, body=String, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/self",
+    operation_id = "self_ssh_certificate_post",
+)]
+/// Sign an OpenSSH public key, issuing a short lived ssh user certificate to the
+/// authenticated account. Returns the certificate in the OpenSSH format.
+pub async fn self_ssh_certificate_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(public_key): Json<String>,
+) -> Result<Json<String>, WebError> {
+    state
+        .qe_w_ref
+        .handle_sshcertificateissue(client_auth_info, public_key, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/self/_application_passwords",
+    responses(
+        (status=200, body=Vec<ApplicationPassword>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/self",
+    operation_id = "self_application_passwords_get",
+)]
+/// List the application passwords of the authenticated person.
+pub async fn self_application_passwords_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<ApplicationPassword>>, WebError> {
+    state
+        .qe_r_ref
+        .handle_list_self_application_passwords(client_auth_info, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/self/_application_passwords",
+    request_body = ApplicationPasswordRequest,
+    responses(
+        (status=200, body=String, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/self",
+    operation_id = "self_application_passwords_post",
+)]
+/// Generate an application password for the authenticated person, which can only be used
+/// to bind to the application with LDAP. Returns the password, it can't be retrieved again.
+pub async fn self_application_passwords_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<ApplicationPasswordRequest>,
+) -> Result<Json<String>, WebError> {
+    state
+        .qe_w_ref
+        .handle_self_application_password_generate(
+            client_auth_info,
+            obj.application,
+            obj.label,
+            kopid.eventid,
+        )
         .await
         .map(Json::from)
         .map_err(WebError::from)
 }
 
 #[utoipa::path(
-    get,
-    path = "/v1/recycle_bin/{id}",
+    delete,
+    path = "/v1/self/_application_passwords/{password_id}",
     responses(
-        (status=200, body=Option<ProtoEntry>, content_type="application/json"),
-        ApiResponseWithout200,
+        DefaultApiResponse,
     ),
     security(("token_jwt" = [])),
-    tag = "v1/recycle_bin",
-    operation_id = "recycle_bin_id_get",
+    tag = "v1/self",
+    operation_id = "self_application_passwords_delete",
 )]
-pub async fn recycle_bin_id_get(
+/// Revoke one application password of the authenticated person.
+pub async fn self_application_passwords_delete(
     State(state): State<ServerState>,
-    Path(id): Path<String>,
+    Path(password_id): Path<Uuid>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
-) -> Result<Json<Option<ProtoEntry>>, WebError> {
-    let filter = filter_all!(f_id(id.as_str()));
-    let attrs = None;
-
+) -> Result<Json<()>, WebError> {
     state
-        .qe_r_ref
-        .handle_internalsearchrecycled(client_auth_info, filter, attrs, kopid.eventid)
+        .qe_w_ref
+        .handle_self_application_password_revoke(client_auth_info, password_id, kopid.eventid)
         .await
-        .map(|mut r| r.pop())
         .map(Json::from)
         .map_err(WebError::from)
 }
 
 #[utoipa::path(
-    post,
-    path = "/v1/recycle_bin/{id}/_revive",
+    get,
+    path = "/v1/ssh/_ca_public_keys",
     responses(
-        DefaultApiResponse,
+        (status=200, body=Vec<String>, content_type="application/json"),
+        ApiResponseWithout200,
     ),
     security(("token_jwt" = [])),
-    tag = "v1/recycle_bin",
-    operation_id = "recycle_bin_revive_id_post",
+    tag = "v1/ssh",
+    operation_id = "ssh_ca_public_keys_get",
 )]
-pub async fn recycle_bin_revive_id_post(
+/// The public keys of the certificate authorities that issue ssh user certificates, for
+/// use with `TrustedUserCAKeys` in sshd.
+pub async fn ssh_ca_public_keys_get(
     State(state): State<ServerState>,
-    Path(id): Path<String>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
-) -> Result<Json<()>, WebError> {
-    let filter = filter_all!(f_id(id.as_str()));
+) -> Result<Json<Vec<String>>, WebError> {
     state
         .qe_w_ref
-        .handle_reviverecycled(client_auth_info, filter, kopid.eventid)
+        .handle_sshcapublickeys(client_auth_info, kopid.eventid)
         .await
         .map(Json::from)
         .map_err(WebError::from)
@@ -2799,24 +3957,30 @@ pub async fn recycle_bin_revive_id_post(
 
 #[utoipa::path(
     get,
-    path = "/v1/self/_applinks",
+    path = "/v1/changes",
+    params(
+        ("cursor" = Option<String>, Query, description="The cursor from a previous response"),
+    ),
     responses(
-        (status=200, body=Vec<AppLink>, content_type="application/json"),
+        (status=200, body=ChangesResponse, content_type="application/json"),
         ApiResponseWithout200,
     ),
     security(("token_jwt" = [])),
-    tag = "v1/self",
-    operation_id = "self_applinks_get",
+    tag = "v1/changes",
+    operation_id = "changes_get",
 )]
-/// Returns your OAuth2 app links for the Web UI
-pub async fn applinks_get(
+/// Returns the uuids of entries that have changed since the provided cursor. If no cursor
+/// is provided, or the cursor is too old, `reset` is set and all cached entries should be
+/// discarded.
+pub async fn changes_get(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
-) -> Result<Json<Vec<AppLink>>, WebError> {
+    Query(query): Query<ChangesQuery>,
+) -> Result<Json<ChangesResponse>, WebError> {
     state
         .qe_r_ref
-        .handle_list_applinks(client_auth_info, kopid.eventid)
+        .handle_changes(client_auth_info, query.cursor, kopid.eventid)
         .await
         .map(Json::from)
         .map_err(WebError::from)
@@ -2877,6 +4041,14 @@ pub async fn auth(
     let maybe_sessionid = state.get_current_auth_session_id(&headers, &jar);
     debug!("Session ID: {:?}", maybe_sessionid);
 
+    // Each new authentication against an account takes from that account's rate limit.
+    if let AuthStep::Init(username) | AuthStep::Init2 { username, .. } = &obj.step {
+        if let Err(retry_after) = check_account_rate_limit(&state, username, kopid.eventid).await {
+            warn!(%username, "Authentication rate limit reached for account");
+            return Ok(rate_limited_response(retry_after));
+        }
+    }
+
     // We probably need to know if we allocate the cookie, that this is a
     // new session, and in that case, anything *except* authrequest init is
     // invalid.
@@ -2970,6 +4142,11 @@ fn auth_session_state_management(
                     debug!("🧩 -> AuthState::Denied");
                     Ok(ProtoAuthState::Denied(reason))
                 }
+                AuthState::CredentialUpdateRequired(token) => {
+                    debug!("🧩 -> AuthState::CredentialUpdateRequired");
+                    jar = jar.remove(Cookie::from(COOKIE_AUTH_SESSION_ID));
+                    Ok(ProtoAuthState::CredentialUpdateRequired(token.to_string()))
+                }
             }
             .map(|state| AuthResponse { sessionid, state })
         }
@@ -3036,6 +4213,83 @@ pub async fn auth_valid(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/auth/break_glass/_challenge",
+    responses(
+        (status=200, body=BreakGlassChallenge, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    tag = "v1/auth",
+    operation_id = "auth_break_glass_challenge_post",
+)]
+/// Request a challenge to be signed by the break glass key.
+pub async fn auth_break_glass_challenge_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+) -> Result<Json<BreakGlassChallenge>, WebError> {
+    state
+        .qe_r_ref
+        .handle_break_glass_begin(kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/auth/break_glass",
+    responses(
+        (status=200, body=BreakGlassResponse, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    request_body = BreakGlassRequest,
+    tag = "v1/auth",
+    operation_id = "auth_break_glass_post",
+)]
+/// Submit the signature of a break glass challenge. If it is valid, a short lived session for
+/// the admin account is issued.
+pub async fn auth_break_glass_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<BreakGlassRequest>,
+) -> Result<Json<BreakGlassResponse>, WebError> {
+    state
+        .qe_r_ref
+        .handle_break_glass(obj, kopid.eventid, client_auth_info)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/auth/break_glass/_code",
+    responses(
+        (status=200, body=BreakGlassResponse, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    request_body = BreakGlassCodeRequest,
+    tag = "v1/auth",
+    operation_id = "auth_break_glass_code_post",
+)]
+/// Submit a recovery code of an emergency account. If it is valid, the code is used up and a
+/// short lived session for the account is issued.
+pub async fn auth_break_glass_code_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<BreakGlassCodeRequest>,
+) -> Result<Json<BreakGlassResponse>, WebError> {
+    state
+        .qe_r_ref
+        .handle_break_glass_code(obj, kopid.eventid, client_auth_info)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/debug/ipinfo",
@@ -3053,6 +4307,60 @@ pub async fn debug_ipinfo(
     Ok(Json::from(ip_addr))
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/debug/entry/{id}",
+    responses(
+        (status = 200, body=EntryInspectResponse, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/debug",
+    operation_id = "debug_entry_inspect",
+)]
+/// Show the internal representation of an entry, including recycled entries and tombstones,
+/// with the change that last modified each attribute. Limited to system administrators.
+pub async fn debug_entry_inspect(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<EntryInspectResponse>, WebError> {
+    state
+        .qe_r_ref
+        .handle_entry_inspect(client_auth_info, id, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/debug/entry/{id}/_history",
+    responses(
+        (status = 200, body=EntryHistoryResponse, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/debug",
+    operation_id = "debug_entry_history",
+)]
+/// Show the change history of an entry from the audit log, along with its internal
+/// representation. Limited to system administrators.
+pub async fn debug_entry_history(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<EntryHistoryResponse>, WebError> {
+    state
+        .qe_r_ref
+        .handle_entry_history(client_auth_info, id, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/jwk/{key_id}",
@@ -3109,6 +4417,10 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/oauth2/_public",
             post(super::v1_oauth2::oauth2_public_post),
         )
+        .route(
+            "/v1/oauth2/_native",
+            post(super::v1_oauth2::oauth2_native_post),
+        )
         .route(
             "/v1/oauth2/:rs_name",
             get(super::v1_oauth2::oauth2_id_get)
@@ -3129,6 +4441,14 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/oauth2/:rs_name/_basic_secret",
             get(super::v1_oauth2::oauth2_id_get_basic_secret),
         )
+        .route(
+            "/v1/oauth2/:rs_name/_basic_secret/_rotate",
+            post(super::v1_oauth2::oauth2_id_basic_secret_rotate),
+        )
+        .route(
+            "/v1/oauth2/:rs_name/_session",
+            get(super::v1_oauth2::oauth2_id_session_get),
+        )
         .route(
             "/v1/oauth2/:rs_name/_scopemap/:group",
             post(super::v1_oauth2::oauth2_id_scopemap_post)
@@ -3148,10 +4468,151 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/oauth2/:rs_name/_claimmap/:claim_name",
             post(super::v1_oauth2::oauth2_id_claimmap_join_post),
         )
+        .route("/v1/audit", get(audit_get))
+        .route(
+            "/v1/webhook",
+            get(super::v1_webhook::webhook_get).post(super::v1_webhook::webhook_post),
+        )
+        .route(
+            "/v1/webhook/:id",
+            get(super::v1_webhook::webhook_id_get).delete(super::v1_webhook::webhook_id_delete),
+        )
+        .route(
+            "/v1/webhook/:id/_attr/:attr",
+            post(super::v1_webhook::webhook_id_attr_post)
+                .put(super::v1_webhook::webhook_id_attr_put)
+                .delete(super::v1_webhook::webhook_id_attr_delete),
+        )
+        .route(
+            "/v1/webhook/:id/_secret",
+            get(super::v1_webhook::webhook_id_secret_get),
+        )
+        .route(
+            "/v1/webhook/:id/_deliveries",
+            get(super::v1_webhook::webhook_id_deliveries_get),
+        )
+        .route(
+            "/v1/announcement",
+            get(super::v1_announcement::announcement_get),
+        )
+        .route(
+            "/v1/announcement/_all",
+            get(super::v1_announcement::announcement_all_get)
+                .post(super::v1_announcement::announcement_all_post),
+        )
+        .route(
+            "/v1/announcement/:id",
+            get(super::v1_announcement::announcement_id_get)
+                .delete(super::v1_announcement::announcement_id_delete),
+        )
+        .route(
+            "/v1/announcement/:id/_attr/:attr",
+            put(super::v1_announcement::announcement_id_attr_put),
+        )
+        .route(
+            "/v1/oidc_upstream",
+            get(super::v1_oidc_upstream::oidc_upstream_get)
+                .post(super::v1_oidc_upstream::oidc_upstream_post),
+        )
+        .route(
+            "/v1/oidc_upstream/:id",
+            get(super::v1_oidc_upstream::oidc_upstream_id_get)
+                .delete(super::v1_oidc_upstream::oidc_upstream_id_delete),
+        )
+        .route(
+            "/v1/oidc_upstream/:id/_attr/:attr",
+            put(super::v1_oidc_upstream::oidc_upstream_id_attr_put)
+                .delete(super::v1_oidc_upstream::oidc_upstream_id_attr_delete),
+        )
+        .route(
+            "/v1/saml2",
+            get(super::v1_saml2::saml2_get).post(super::v1_saml2::saml2_post),
+        )
+        .route(
+            "/v1/saml2/:id",
+            get(super::v1_saml2::saml2_id_get).delete(super::v1_saml2::saml2_id_delete),
+        )
+        .route(
+            "/v1/saml2/:id/_attr/:attr",
+            put(super::v1_saml2::saml2_id_attr_put).delete(super::v1_saml2::saml2_id_attr_delete),
+        )
+        .route(
+            "/v1/saml2/:sp_name/_group_map/:group",
+            post(super::v1_saml2::saml2_id_group_map_post)
+                .delete(super::v1_saml2::saml2_id_group_map_delete),
+        )
+        .route(
+            "/saml2/:sp_name/metadata",
+            get(super::v1_saml2::saml2_metadata_get),
+        )
+        .route(
+            "/v1/hbac_rule",
+            get(super::v1_hbac::hbac_rule_get).post(super::v1_hbac::hbac_rule_post),
+        )
+        .route(
+            "/v1/hbac_rule/:id",
+            get(super::v1_hbac::hbac_rule_id_get).delete(super::v1_hbac::hbac_rule_id_delete),
+        )
+        .route(
+            "/v1/hbac_rule/:id/_attr/:attr",
+            post(super::v1_hbac::hbac_rule_id_attr_post)
+                .put(super::v1_hbac::hbac_rule_id_attr_put)
+                .delete(super::v1_hbac::hbac_rule_id_attr_delete),
+        )
+        .route(
+            "/v1/host",
+            get(super::v1_host::host_get).post(super::v1_host::host_post),
+        )
+        .route("/v1/host/_enroll", post(super::v1_host::host_enroll_post))
+        .route(
+            "/v1/host/_rotate_credential",
+            post(super::v1_host::host_rotate_credential_post),
+        )
+        .route(
+            "/v1/host/_kerberos_keytab",
+            post(super::v1_host::host_kerberos_keytab_post),
+        )
+        .route(
+            "/v1/host/_kerberos_ticket",
+            post(super::v1_host::host_kerberos_ticket_post),
+        )
+        .route(
+            "/v1/host/:id",
+            get(super::v1_host::host_id_get).delete(super::v1_host::host_id_delete),
+        )
+        .route(
+            "/v1/host/:id/_attr/:attr",
+            post(super::v1_host::host_id_attr_post)
+                .put(super::v1_host::host_id_attr_put)
+                .delete(super::v1_host::host_id_attr_delete),
+        )
+        .route(
+            "/v1/host/:id/_join_token",
+            post(super::v1_host::host_id_join_token_post),
+        )
+        .route(
+            "/v1/host_group",
+            get(super::v1_hostgroup::host_group_get).post(super::v1_hostgroup::host_group_post),
+        )
+        .route(
+            "/v1/host_group/:id",
+            get(super::v1_hostgroup::host_group_id_get)
+                .delete(super::v1_hostgroup::host_group_id_delete),
+        )
+        .route(
+            "/v1/host_group/:id/_attr/:attr",
+            post(super::v1_hostgroup::host_group_id_attr_post)
+                .put(super::v1_hostgroup::host_group_id_attr_put)
+                .delete(super::v1_hostgroup::host_group_id_attr_delete),
+        )
         .route("/v1/raw/create", post(raw_create))
         .route("/v1/raw/modify", post(raw_modify))
         .route("/v1/raw/delete", post(raw_delete))
         .route("/v1/raw/search", post(raw_search))
+        .route(
+            "/v1/import",
+            post(bulk_import).layer(DefaultBodyLimit::max(DEFAULT_BULK_IMPORT_BYTES)),
+        )
         .route("/v1/schema", get(schema_get))
         .route(
             "/v1/schema/attributetype",
@@ -3190,12 +4651,26 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         // )
         // Applinks are the list of apps this account can access.
         .route("/v1/self/_applinks", get(applinks_get))
+        .route("/v1/self/_ssh_certificate", post(self_ssh_certificate_post))
+        .route(
+            "/v1/self/_application_passwords",
+            get(self_application_passwords_get).post(self_application_passwords_post),
+        )
+        .route(
+            "/v1/self/_application_passwords/:password_id",
+            delete(self_application_passwords_delete),
+        )
+        .route("/v1/ssh/_ca_public_keys", get(ssh_ca_public_keys_get))
+        // Changes to entries, for clients that cache them.
+        .route("/v1/changes", get(changes_get))
         // Person routes
         .route("/v1/person", get(person_get).post(person_post))
+        .route("/v1/person/_contractor", post(person_contractor_post))
         .route("/v1/person/_search/:id", get(person_search_id))
         .route(
             "/v1/person/:id",
             get(person_id_get)
+                .put(person_id_put)
                 .patch(person_id_patch)
                 .delete(person_id_delete),
         )
@@ -3214,6 +4689,10 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/person/:id/_credential/_status",
             get(person_get_id_credential_status),
         )
+        .route(
+            "/v1/person/:id/_credential/_softlock",
+            get(person_get_id_credential_softlock).delete(person_delete_id_credential_softlock),
+        )
         .route(
             "/v1/person/:id/_credential/_update",
             get(person_id_credential_update_get),
@@ -3224,7 +4703,16 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         )
         .route(
             "/v1/person/:id/_credential/_update_intent",
-            get(person_id_credential_update_intent_get),
+            get(person_id_credential_update_intent_get)
+                .post(person_id_credential_update_intent_post),
+        )
+        .route(
+            "/v1/person/:id/_credential/_intents",
+            get(person_id_credential_intents_get),
+        )
+        .route(
+            "/v1/person/:id/_credential/_intents/:token_ref",
+            delete(person_id_credential_intents_delete),
         )
         .route(
             "/v1/person/:id/_ssh_pubkeys",
@@ -3240,6 +4728,11 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
                 .post(person_id_radius_post)
                 .delete(person_id_radius_delete),
         )
+        .route(
+            "/v1/person/:id/_radius/_certificate",
+            post(person_id_radius_certificate_post),
+        )
+        .route("/v1/radius/_ca_certificate", get(radius_ca_certificate_get))
         .route("/v1/person/:id/_unix", post(person_id_unix_post))
         .route(
             "/v1/person/:id/_unix/_credential",
@@ -3249,6 +4742,10 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/person/:id/_identify_user",
             post(person_identify_user_post),
         )
+        .route(
+            "/v1/person/:id/_impersonate",
+            post(person_id_impersonate_post),
+        )
         // Service accounts
         .route(
             "/v1/service_account",
@@ -3261,6 +4758,7 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         .route(
             "/v1/service_account/:id",
             get(service_account_id_get)
+                .put(service_account_id_put)
                 .delete(service_account_id_delete)
                 .patch(service_account_id_patch),
         )
@@ -3285,6 +4783,14 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/service_account/:id/_api_token/:token_id",
             delete(service_account_api_token_delete),
         )
+        .route(
+            "/v1/service_account/:id/_jwt_public_key",
+            post(service_account_jwt_public_key_post),
+        )
+        .route(
+            "/v1/service_account/:id/_jwt_public_key/:label",
+            delete(service_account_jwt_public_key_delete),
+        )
         // .route(
         //     "/v1/service_account/:id/_credential",
         //     get(|| async { "TODO" }),
@@ -3336,6 +4842,14 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/account/:id/_user_auth_token",
             get(account_id_user_auth_token_get),
         )
+        .route(
+            "/v1/account/:id/_oauth2_session",
+            get(account_id_oauth2_session_get),
+        )
+        .route(
+            "/v1/account/:id/_account_policy",
+            get(account_id_account_policy_get),
+        )
         .route(
             "/v1/account/:id/_user_auth_token/:token_id",
             delete(account_user_auth_token_delete),
@@ -3344,7 +4858,19 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/credential/_exchange_intent",
             post(credential_update_exchange_intent),
         )
+        .route(
+            "/v1/credential/_exchange_expired",
+            post(credential_update_exchange_expired),
+        )
         .route("/v1/credential/_status", post(credential_update_status))
+        .route(
+            "/v1/credential/_passphrase",
+            post(credential_update_suggest_passphrase),
+        )
+        .route(
+            "/v1/credential/_check_password",
+            post(credential_update_check_password),
+        )
         .route("/v1/credential/_update", post(credential_update_update))
         .route("/v1/credential/_commit", post(credential_update_commit))
         .route("/v1/credential/_cancel", post(credential_update_cancel))
@@ -3362,11 +4888,32 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         )
         .route("/v1/group/:id/_unix/_token", get(group_id_unix_token_get))
         .route("/v1/group/:id/_unix", post(group_id_unix_post))
+        .route(
+            "/v1/group/:id/_temporary_members",
+            post(group_id_temporary_members_post),
+        )
+        .route(
+            "/v1/group/:id/_membership_request",
+            post(group_id_membership_request_post),
+        )
+        .route(
+            "/v1/group_membership_request",
+            get(group_membership_request_get),
+        )
+        .route(
+            "/v1/group_membership_request/:id/_approve",
+            post(group_membership_request_id_approve_post),
+        )
+        .route(
+            "/v1/group_membership_request/:id/_deny",
+            post(group_membership_request_id_deny_post),
+        )
         .route("/v1/group", get(group_get).post(group_post))
         .route("/v1/group/_search/:id", get(group_search_id))
         .route(
             "/v1/group/:id",
             get(group_id_get)
+                .put(group_id_put)
                 .patch(group_id_patch)
                 .delete(group_id_delete),
         )
@@ -3379,6 +4926,11 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         )
         .with_state(state.clone())
         .route("/v1/system", get(system_get))
+        .route("/v1/system/_migrations", get(system_migrations_get))
+        .route(
+            "/v1/system/_access_control_preview",
+            get(system_access_control_preview_get),
+        )
         .route(
             "/v1/system/_attr/:attr",
             get(system_attr_get)
@@ -3400,10 +4952,21 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
         // )
         .route("/v1/auth", post(auth))
         .route(V1_AUTH_VALID, get(auth_valid))
+        .route(
+            "/v1/auth/break_glass/_challenge",
+            post(auth_break_glass_challenge_post),
+        )
+        .route("/v1/auth/break_glass", post(auth_break_glass_post))
+        .route(
+            "/v1/auth/break_glass/_code",
+            post(auth_break_glass_code_post),
+        )
         .route("/v1/logout", get(logout))
         .route("/v1/reauth", post(reauth))
         .with_state(state.clone())
         .layer(from_fn(dont_cache_me))
         .merge(cacheable_routes(state))
         .route("/v1/debug/ipinfo", get(debug_ipinfo))
+        .route("/v1/debug/entry/:id", get(debug_entry_inspect))
+        .route("/v1/debug/entry/:id/_history", get(debug_entry_history))
 }
@@ -0,0 +1,168 @@
+use super::apidocs::response_schema::{ApiResponseWithout200, DefaultApiResponse};
+use super::errors::WebError;
+use super::middleware::KOpId;
+use super::v1::{
+    json_rest_event_delete_id, json_rest_event_get, json_rest_event_get_id, json_rest_event_post,
+    json_rest_event_put_attr,
+};
+use super::ServerState;
+
+use crate::https::extractors::{DomainInfo, VerifiedClientInformation};
+use axum::extract::{Path, State};
+use axum::{Extension, Json};
+use kanidm_proto::internal::Announcement;
+use kanidm_proto::v1::Entry as ProtoEntry;
+use kanidmd_lib::prelude::*;
+
+fn announcement_filter() -> Filter<FilterInvalid> {
+    filter_all!(f_eq(Attribute::Class, EntryClass::Announcement.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/announcement",
+    responses(
+        (status = 200, content_type="application/json", body=Vec<Announcement>),
+        ApiResponseWithout200,
+    ),
+    tag = "v1/announcement",
+    operation_id = "announcement_get"
+)]
+/// Lists the announcements that are currently active, most severe first. These are shown on the
+/// login page, so this does not require authentication.
+pub(crate) async fn announcement_get(
+    DomainInfo(domain_info): DomainInfo,
+) -> Result<Json<Vec<Announcement>>, WebError> {
+    Ok(Json(
+        domain_info
+            .active_announcements()
+            .into_iter()
+            .cloned()
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/announcement/_all",
+    responses(
+        (status = 200, content_type="application/json", body=Vec<ProtoEntry>),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/announcement",
+    operation_id = "announcement_all_get"
+)]
+/// Lists all announcements, including those that have not started or have ended
+pub(crate) async fn announcement_all_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<ProtoEntry>>, WebError> {
+    json_rest_event_get(state, None, announcement_filter(), kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/announcement/_all",
+    request_body=ProtoEntry,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/announcement",
+    operation_id = "announcement_all_post"
+)]
+/// Create a new announcement
+pub(crate) async fn announcement_all_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<ProtoEntry>,
+) -> Result<Json<()>, WebError> {
+    let classes = vec![
+        EntryClass::Announcement.to_string(),
+        EntryClass::Object.to_string(),
+    ];
+    json_rest_event_post(state, classes, obj, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/announcement/{id}",
+    responses(
+        (status = 200, body=Option<ProtoEntry>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/announcement",
+    operation_id = "announcement_id_get"
+)]
+/// Get the details of an announcement
+pub(crate) async fn announcement_id_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Option<ProtoEntry>>, WebError> {
+    json_rest_event_get_id(
+        state,
+        id,
+        announcement_filter(),
+        None,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/announcement/{id}",
+    responses(
+        DefaultApiResponse,
+        (status = 404),
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/announcement",
+    operation_id = "announcement_id_delete"
+)]
+/// Delete an announcement
+pub(crate) async fn announcement_id_delete(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    json_rest_event_delete_id(state, id, announcement_filter(), kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/announcement/{id}/_attr/{attr}",
+    request_body=Vec<String>,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/announcement",
+    operation_id = "announcement_id_attr_put",
+)]
+pub(crate) async fn announcement_id_attr_put(
+    Path((id, attr)): Path<(String, String)>,
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(values): Json<Vec<String>>,
+) -> Result<Json<()>, WebError> {
+    json_rest_event_put_attr(
+        state,
+        id,
+        attr,
+        announcement_filter(),
+        values,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
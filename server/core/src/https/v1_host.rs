@@ -0,0 +1,333 @@
+use super::apidocs::response_schema::{ApiResponseWithout200, DefaultApiResponse};
+use super::errors::WebError;
+use super::middleware::KOpId;
+use super::v1::{
+    json_rest_event_delete_id, json_rest_event_delete_id_attr, json_rest_event_get,
+    json_rest_event_get_id, json_rest_event_post, json_rest_event_post_id_attr,
+    json_rest_event_put_attr,
+};
+use super::ServerState;
+
+use crate::https::extractors::VerifiedClientInformation;
+use axum::extract::{Path, State};
+use axum::{Extension, Json};
+use kanidm_proto::v1::{
+    Entry as ProtoEntry, HostEnrollRequest, KerberosKeytab, KerberosTicket, KerberosTicketRequest,
+};
+use kanidmd_lib::prelude::*;
+
+fn host_filter() -> Filter<FilterInvalid> {
+    filter_all!(f_eq(Attribute::Class, EntryClass::Host.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/host",
+    responses(
+        (status = 200,content_type="application/json", body=Vec<ProtoEntry>),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_get"
+)]
+/// Lists all the hosts
+pub(crate) async fn host_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<ProtoEntry>>, WebError> {
+    json_rest_event_get(state, None, host_filter(), kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/host",
+    request_body=ProtoEntry,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_post"
+)]
+/// Create a new host. A join token must be issued before a machine can enroll as the host
+pub(crate) async fn host_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<ProtoEntry>,
+) -> Result<Json<()>, WebError> {
+    let classes = vec![
+        EntryClass::Host.to_string(),
+        EntryClass::ServiceAccount.to_string(),
+        EntryClass::Account.to_string(),
+        EntryClass::Object.to_string(),
+    ];
+    json_rest_event_post(state, classes, obj, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/host/{id}",
+    responses(
+        (status = 200, body=Option<ProtoEntry>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_id_get"
+)]
+/// Get the details of a host
+pub(crate) async fn host_id_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Option<ProtoEntry>>, WebError> {
+    json_rest_event_get_id(state, id, host_filter(), None, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/host/{id}",
+    responses(
+        DefaultApiResponse,
+        (status = 404),
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_id_delete"
+)]
+/// Delete a host, revoking its credential
+pub(crate) async fn host_id_delete(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    json_rest_event_delete_id(state, id, host_filter(), kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/host/{id}/_attr/{attr}",
+    request_body=Vec<String>,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_id_attr_post",
+)]
+pub(crate) async fn host_id_attr_post(
+    Path((id, attr)): Path<(String, String)>,
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(values): Json<Vec<String>>,
+) -> Result<Json<()>, WebError> {
+    json_rest_event_post_id_attr(
+        state,
+        id,
+        attr,
+        host_filter(),
+        values,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/host/{id}/_attr/{attr}",
+    request_body=Vec<String>,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_id_attr_put",
+)]
+pub(crate) async fn host_id_attr_put(
+    Path((id, attr)): Path<(String, String)>,
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(values): Json<Vec<String>>,
+) -> Result<Json<()>, WebError> {
+    json_rest_event_put_attr(
+        state,
+        id,
+        attr,
+        host_filter(),
+        values,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/host/{id}/_attr/{attr}",
+    request_body=Option<Vec<String>>,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_id_attr_delete",
+)]
+pub(crate) async fn host_id_attr_delete(
+    Path((id, attr)): Path<(String, String)>,
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    values: Option<Json<Vec<String>>>,
+) -> Result<Json<()>, WebError> {
+    let values = values.map(|v| v.0);
+    json_rest_event_delete_id_attr(
+        state,
+        id,
+        attr,
+        host_filter(),
+        values,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/host/{id}/_join_token",
+    request_body=Option<u64>,
+    responses(
+        (status=200, body=String, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_id_join_token_post",
+)]
+/// Issue a one time join token that allows a machine to enroll as this host. The request
+/// may contain the number of seconds that the token is valid for.
+pub(crate) async fn host_id_join_token_post(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(ttl): Json<Option<u64>>,
+) -> Result<Json<String>, WebError> {
+    state
+        .qe_w_ref
+        .handle_host_join_token_generate(client_auth_info, id, ttl, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/host/_enroll",
+    request_body=HostEnrollRequest,
+    responses(
+        (status=200, body=String, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    tag = "v1/host",
+    operation_id = "host_enroll_post",
+)]
+/// Enroll a machine as a host by exchanging a join token for the credential of the host.
+pub(crate) async fn host_enroll_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    Json(request): Json<HostEnrollRequest>,
+) -> Result<Json<String>, WebError> {
+    state
+        .qe_w_ref
+        .handle_host_enroll(request, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/host/_rotate_credential",
+    responses(
+        (status=200, body=String, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_rotate_credential_post",
+)]
+/// Issue a new credential to the authenticated host, revoking the credential it authenticated
+/// with.
+pub(crate) async fn host_rotate_credential_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<String>, WebError> {
+    state
+        .qe_w_ref
+        .handle_host_credential_rotate(client_auth_info, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/host/_kerberos_keytab",
+    responses(
+        (status=200, body=KerberosKeytab, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_kerberos_keytab_post",
+)]
+/// The keytab of the kerberos service principals of the authenticated host.
+pub(crate) async fn host_kerberos_keytab_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<KerberosKeytab>, WebError> {
+    state
+        .qe_w_ref
+        .handle_host_kerberos_keytab(client_auth_info, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/host/_kerberos_ticket",
+    request_body=KerberosTicketRequest,
+    responses(
+        (status=200, body=KerberosTicket, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/host",
+    operation_id = "host_kerberos_ticket_post",
+)]
+/// Issue a kerberos service ticket to an account that has logged in to the authenticated host.
+pub(crate) async fn host_kerberos_ticket_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(request): Json<KerberosTicketRequest>,
+) -> Result<Json<KerberosTicket>, WebError> {
+    state
+        .qe_w_ref
+        .handle_host_kerberos_ticket(client_auth_info, request, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
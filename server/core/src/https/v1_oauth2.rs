@@ -11,8 +11,10 @@ use super::ServerState;
 use crate::https::extractors::VerifiedClientInformation;
 use axum::extract::{Path, State};
 use axum::{Extension, Json};
-use kanidm_proto::internal::{ImageType, ImageValue, Oauth2ClaimMapJoin};
-use kanidm_proto::v1::Entry as ProtoEntry;
+use kanidm_proto::internal::{
+    ImageType, ImageValue, Oauth2BasicSecretRotateRequest, Oauth2ClaimMapJoin,
+};
+use kanidm_proto::v1::{Entry as ProtoEntry, Oauth2SessionStatus};
 use kanidmd_lib::prelude::*;
 use kanidmd_lib::valueset::image::ImageValueThings;
 use sketching::admin_error;
@@ -95,6 +97,34 @@ pub(crate) async fn oauth2_public_post(
     json_rest_event_post(state, classes, obj, kopid, client_auth_info).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/oauth2/_native",
+    request_body=ProtoEntry,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/oauth2",
+    operation_id = "oauth2_native_post"
+)]
+/// Create a new Public OAuth2 client for a native application
+pub(crate) async fn oauth2_native_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<ProtoEntry>,
+) -> Result<Json<()>, WebError> {
+    let classes = vec![
+        EntryClass::OAuth2ResourceServer.to_string(),
+        EntryClass::OAuth2ResourceServerPublic.to_string(),
+        EntryClass::OAuth2ResourceServerNative.to_string(),
+        EntryClass::Account.to_string(),
+        EntryClass::Object.to_string(),
+    ];
+    json_rest_event_post(state, classes, obj, kopid, client_auth_info).await
+}
+
 #[utoipa::path(
     get,
     path = "/v1/oauth2/{rs_name}",
@@ -151,6 +181,67 @@ pub(crate) async fn oauth2_id_get_basic_secret(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/oauth2/{rs_name}/_basic_secret/_rotate",
+    request_body=Oauth2BasicSecretRotateRequest,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/oauth2",
+    operation_id = "oauth2_id_basic_secret_rotate"
+)]
+/// Replace the basic secret of a given OAuth2 Resource Server. The previous secret remains valid
+/// for a period so that the client can be updated.
+#[instrument(level = "info", skip(state))]
+pub(crate) async fn oauth2_id_basic_secret_rotate(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(rs_name): Path<String>,
+    Json(rotate_req): Json<Oauth2BasicSecretRotateRequest>,
+) -> Result<Json<()>, WebError> {
+    let filter = oauth2_id(&rs_name);
+    state
+        .qe_w_ref
+        .handle_oauth2_basic_secret_rotate(
+            client_auth_info,
+            filter,
+            rotate_req.previous_secret_validity,
+            kopid.eventid,
+        )
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/oauth2/{rs_name}/_session",
+    responses(
+        (status = 200, content_type="application/json", body=Vec<Oauth2SessionStatus>),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/oauth2",
+    operation_id = "oauth2_id_session_get"
+)]
+/// List the sessions issued to an OAuth2 Resource Server by the client credentials grant.
+pub(crate) async fn oauth2_id_session_get(
+    State(state): State<ServerState>,
+    Path(rs_name): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<Oauth2SessionStatus>>, WebError> {
+    state
+        .qe_r_ref
+        .handle_oauth2_session_get(client_auth_info, rs_name, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     patch,
     path = "/v1/oauth2/{rs_name}",
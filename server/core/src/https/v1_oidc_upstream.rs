@@ -0,0 +1,175 @@
+use super::apidocs::response_schema::{ApiResponseWithout200, DefaultApiResponse};
+use super::errors::WebError;
+use super::middleware::KOpId;
+use super::v1::{
+    json_rest_event_delete_id, json_rest_event_delete_id_attr, json_rest_event_get,
+    json_rest_event_get_id, json_rest_event_post, json_rest_event_put_attr,
+};
+use super::ServerState;
+
+use crate::https::extractors::VerifiedClientInformation;
+use axum::extract::{Path, State};
+use axum::{Extension, Json};
+use kanidm_proto::v1::Entry as ProtoEntry;
+use kanidmd_lib::prelude::*;
+
+fn oidc_upstream_filter() -> Filter<FilterInvalid> {
+    filter_all!(f_eq(Attribute::Class, EntryClass::OidcUpstream.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/oidc_upstream",
+    responses(
+        (status = 200,content_type="application/json", body=Vec<ProtoEntry>),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/oidc_upstream",
+    operation_id = "oidc_upstream_get"
+)]
+/// Lists all the upstream OpenID Connect providers
+pub(crate) async fn oidc_upstream_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<ProtoEntry>>, WebError> {
+    json_rest_event_get(state, None, oidc_upstream_filter(), kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/oidc_upstream",
+    request_body=ProtoEntry,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/oidc_upstream",
+    operation_id = "oidc_upstream_post"
+)]
+/// Create a new upstream OpenID Connect provider that people may login with
+pub(crate) async fn oidc_upstream_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<ProtoEntry>,
+) -> Result<Json<()>, WebError> {
+    let classes = vec![
+        EntryClass::OidcUpstream.to_string(),
+        EntryClass::Object.to_string(),
+    ];
+    json_rest_event_post(state, classes, obj, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/oidc_upstream/{id}",
+    responses(
+        (status = 200, body=Option<ProtoEntry>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/oidc_upstream",
+    operation_id = "oidc_upstream_id_get"
+)]
+/// Get the details of an upstream OpenID Connect provider
+pub(crate) async fn oidc_upstream_id_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Option<ProtoEntry>>, WebError> {
+    json_rest_event_get_id(
+        state,
+        id,
+        oidc_upstream_filter(),
+        None,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/oidc_upstream/{id}",
+    responses(
+        DefaultApiResponse,
+        (status = 404),
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/oidc_upstream",
+    operation_id = "oidc_upstream_id_delete"
+)]
+/// Delete an upstream OpenID Connect provider
+pub(crate) async fn oidc_upstream_id_delete(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    json_rest_event_delete_id(state, id, oidc_upstream_filter(), kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/oidc_upstream/{id}/_attr/{attr}",
+    request_body=Vec<String>,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/oidc_upstream",
+    operation_id = "oidc_upstream_id_attr_put",
+)]
+pub(crate) async fn oidc_upstream_id_attr_put(
+    Path((id, attr)): Path<(String, String)>,
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(values): Json<Vec<String>>,
+) -> Result<Json<()>, WebError> {
+    json_rest_event_put_attr(
+        state,
+        id,
+        attr,
+        oidc_upstream_filter(),
+        values,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/oidc_upstream/{id}/_attr/{attr}",
+    request_body=Option<Vec<String>>,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/oidc_upstream",
+    operation_id = "oidc_upstream_id_attr_delete",
+)]
+/// Remove values from an attribute of an upstream OpenID Connect provider
+pub(crate) async fn oidc_upstream_id_attr_delete(
+    Path((id, attr)): Path<(String, String)>,
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    values: Option<Json<Vec<String>>>,
+) -> Result<Json<()>, WebError> {
+    let values = values.map(|v| v.0);
+    json_rest_event_delete_id_attr(
+        state,
+        id,
+        attr,
+        oidc_upstream_filter(),
+        values,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
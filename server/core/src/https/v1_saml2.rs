@@ -0,0 +1,265 @@
+use super::apidocs::response_schema::{ApiResponseWithout200, DefaultApiResponse};
+use super::errors::WebError;
+use super::middleware::KOpId;
+use super::v1::{
+    json_rest_event_delete_id, json_rest_event_delete_id_attr, json_rest_event_get,
+    json_rest_event_get_id, json_rest_event_post, json_rest_event_put_attr,
+};
+use super::ServerState;
+
+use crate::https::extractors::VerifiedClientInformation;
+use axum::extract::{Path, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use kanidm_proto::v1::Entry as ProtoEntry;
+use kanidmd_lib::prelude::*;
+
+fn saml2_filter() -> Filter<FilterInvalid> {
+    filter_all!(f_eq(
+        Attribute::Class,
+        EntryClass::Saml2ServiceProvider.into()
+    ))
+}
+
+/// Get a filter matching a given SAML service provider
+fn saml2_id(sp_name: &str) -> Filter<FilterInvalid> {
+    filter_all!(f_and!([
+        f_eq(Attribute::Class, EntryClass::Saml2ServiceProvider.into()),
+        f_eq(Attribute::Name, PartialValue::new_iname(sp_name))
+    ]))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/saml2",
+    responses(
+        (status = 200,content_type="application/json", body=Vec<ProtoEntry>),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/saml2",
+    operation_id = "saml2_get"
+)]
+/// Lists all the SAML service providers
+pub(crate) async fn saml2_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Vec<ProtoEntry>>, WebError> {
+    json_rest_event_get(state, None, saml2_filter(), kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/saml2",
+    request_body=ProtoEntry,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/saml2",
+    operation_id = "saml2_post"
+)]
+/// Create a new SAML service provider. A signing key is generated for it.
+pub(crate) async fn saml2_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(obj): Json<ProtoEntry>,
+) -> Result<Json<()>, WebError> {
+    let classes = vec![
+        EntryClass::Saml2ServiceProvider.to_string(),
+        EntryClass::KeyObject.to_string(),
+        EntryClass::KeyObjectSaml2Signing.to_string(),
+        EntryClass::Object.to_string(),
+    ];
+    json_rest_event_post(state, classes, obj, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/saml2/{id}",
+    responses(
+        (status = 200, body=Option<ProtoEntry>, content_type="application/json"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/saml2",
+    operation_id = "saml2_id_get"
+)]
+/// Get the details of a SAML service provider
+pub(crate) async fn saml2_id_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<Option<ProtoEntry>>, WebError> {
+    json_rest_event_get_id(state, id, saml2_filter(), None, kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/saml2/{id}",
+    responses(
+        DefaultApiResponse,
+        (status = 404),
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/saml2",
+    operation_id = "saml2_id_delete"
+)]
+/// Delete a SAML service provider
+pub(crate) async fn saml2_id_delete(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    json_rest_event_delete_id(state, id, saml2_filter(), kopid, client_auth_info).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/saml2/{id}/_attr/{attr}",
+    request_body=Vec<String>,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/saml2",
+    operation_id = "saml2_id_attr_put",
+)]
+/// Set the values of an attribute of a SAML service provider
+pub(crate) async fn saml2_id_attr_put(
+    Path((id, attr)): Path<(String, String)>,
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(values): Json<Vec<String>>,
+) -> Result<Json<()>, WebError> {
+    json_rest_event_put_attr(
+        state,
+        id,
+        attr,
+        saml2_filter(),
+        values,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/saml2/{id}/_attr/{attr}",
+    request_body=Option<Vec<String>>,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/saml2",
+    operation_id = "saml2_id_attr_delete",
+)]
+/// Remove values from an attribute of a SAML service provider
+pub(crate) async fn saml2_id_attr_delete(
+    Path((id, attr)): Path<(String, String)>,
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    values: Option<Json<Vec<String>>>,
+) -> Result<Json<()>, WebError> {
+    let values = values.map(|v| v.0);
+    json_rest_event_delete_id_attr(
+        state,
+        id,
+        attr,
+        saml2_filter(),
+        values,
+        kopid,
+        client_auth_info,
+    )
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/saml2/{sp_name}/_group_map/{group}",
+    request_body=Vec<String>,
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/saml2",
+    operation_id = "saml2_id_group_map_post"
+)]
+/// Set the values of the groups attribute that members of a group are given
+pub(crate) async fn saml2_id_group_map_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path((sp_name, group)): Path<(String, String)>,
+    Json(values): Json<Vec<String>>,
+) -> Result<Json<()>, WebError> {
+    let filter = saml2_id(&sp_name);
+
+    state
+        .qe_w_ref
+        .handle_saml2_group_map_update(client_auth_info, group, values, filter, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/saml2/{sp_name}/_group_map/{group}",
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "v1/saml2",
+    operation_id = "saml2_id_group_map_delete"
+)]
+/// Remove the group map of a group, so that its members can no longer sign in
+pub(crate) async fn saml2_id_group_map_delete(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path((sp_name, group)): Path<(String, String)>,
+) -> Result<Json<()>, WebError> {
+    let filter = saml2_id(&sp_name);
+
+    state
+        .qe_w_ref
+        .handle_saml2_group_map_delete(client_auth_info, group, filter, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/saml2/{sp_name}/metadata",
+    responses(
+        (status = 200, content_type="application/samlmetadata+xml", body=String),
+        ApiResponseWithout200,
+    ),
+    tag = "v1/saml2",
+    operation_id = "saml2_metadata_get"
+)]
+/// The metadata of the identity provider that a SAML service provider is configured with
+pub(crate) async fn saml2_metadata_get(
+    State(state): State<ServerState>,
+    Path(sp_name): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+) -> Result<Response, WebError> {
+    state
+        .qe_r_ref
+        .handle_saml2_metadata(sp_name, kopid.eventid)
+        .await
+        .map(|metadata| {
+            ([(CONTENT_TYPE, "application/samlmetadata+xml")], metadata).into_response()
+        })
+        .map_err(WebError::from)
+}
@@ -8,11 +8,17 @@ use super::v1::{
 use super::ServerState;
 use crate::https::extractors::VerifiedClientInformation;
 use axum::extract::{rejection::JsonRejection, DefaultBodyLimit, Path, Query, State};
+use axum::http::StatusCode;
 use axum::response::{Html, IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
 use kanidm_proto::scim_v1::{
-    server::ScimEntryKanidm, ScimEntryGetQuery, ScimSyncRequest, ScimSyncState,
+    provision::{
+        ScimPatchRequest, ScimProvisionGroup, ScimProvisionGroupListResponse,
+        ScimProvisionListQuery, ScimProvisionUser, ScimProvisionUserListResponse,
+    },
+    server::ScimEntryKanidm,
+    ScimEntryGetQuery, ScimSyncRequest, ScimSyncState,
 };
 use kanidm_proto::v1::Entry as ProtoEntry;
 use kanidmd_lib::prelude::*;
@@ -383,6 +389,336 @@ async fn scim_person_id_get(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Users",
+    params(
+        ("filter" = Option<String>, Query, description = "A SCIM filter, such as userName eq \"bjensen\""),
+        ("startIndex" = Option<usize>, Query, description = "The 1-based index of the first result"),
+        ("count" = Option<usize>, Query, description = "The maximum number of results to return"),
+    ),
+    responses(
+        (status = 200, content_type="application/scim+json", body=ScimProvisionUserListResponse),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_users_get"
+)]
+/// List or filter the users that may be provisioned.
+async fn scim_provision_users_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<ScimProvisionListQuery>,
+) -> Result<Json<ScimProvisionUserListResponse>, WebError> {
+    state
+        .qe_r_ref
+        .handle_scim_provision_user_list(client_auth_info, kopid.eventid, query)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/scim/v2/Users",
+    request_body=ScimProvisionUser,
+    responses(
+        (status = 201, content_type="application/scim+json", body=ScimProvisionUser),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_users_post"
+)]
+/// Provision a new user.
+async fn scim_provision_users_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(user): Json<ScimProvisionUser>,
+) -> Result<(StatusCode, Json<ScimProvisionUser>), WebError> {
+    state
+        .qe_w_ref
+        .handle_scim_provision_user_create(client_auth_info, kopid.eventid, user)
+        .await
+        .map(|user| (StatusCode::CREATED, Json::from(user)))
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Users/{id}",
+    responses(
+        (status = 200, content_type="application/scim+json", body=ScimProvisionUser),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_user_id_get"
+)]
+async fn scim_provision_user_id_get(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<ScimProvisionUser>, WebError> {
+    state
+        .qe_r_ref
+        .handle_scim_provision_user_get(client_auth_info, kopid.eventid, id)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    put,
+    path = "/scim/v2/Users/{id}",
+    request_body=ScimProvisionUser,
+    responses(
+        (status = 200, content_type="application/scim+json", body=ScimProvisionUser),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_user_id_put"
+)]
+/// Replace the attributes of a user.
+async fn scim_provision_user_id_put(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(user): Json<ScimProvisionUser>,
+) -> Result<Json<ScimProvisionUser>, WebError> {
+    state
+        .qe_w_ref
+        .handle_scim_provision_user_replace(client_auth_info, kopid.eventid, id, user)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/scim/v2/Users/{id}",
+    request_body=ScimPatchRequest,
+    responses(
+        (status = 200, content_type="application/scim+json", body=ScimProvisionUser),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_user_id_patch"
+)]
+/// Apply a set of changes to a user.
+async fn scim_provision_user_id_patch(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(patch): Json<ScimPatchRequest>,
+) -> Result<Json<ScimProvisionUser>, WebError> {
+    state
+        .qe_w_ref
+        .handle_scim_provision_user_patch(client_auth_info, kopid.eventid, id, patch)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/scim/v2/Users/{id}",
+    responses(
+        (status = 204, description = "The user was deleted"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_user_id_delete"
+)]
+/// Deprovision a user.
+async fn scim_provision_user_id_delete(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<StatusCode, WebError> {
+    state
+        .qe_w_ref
+        .handle_scim_provision_user_delete(client_auth_info, kopid.eventid, id)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Groups",
+    params(
+        ("filter" = Option<String>, Query, description = "A SCIM filter, such as displayName eq \"staff\""),
+        ("startIndex" = Option<usize>, Query, description = "The 1-based index of the first result"),
+        ("count" = Option<usize>, Query, description = "The maximum number of results to return"),
+    ),
+    responses(
+        (status = 200, content_type="application/scim+json", body=ScimProvisionGroupListResponse),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_groups_get"
+)]
+/// List or filter the groups that may be provisioned.
+async fn scim_provision_groups_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<ScimProvisionListQuery>,
+) -> Result<Json<ScimProvisionGroupListResponse>, WebError> {
+    state
+        .qe_r_ref
+        .handle_scim_provision_group_list(client_auth_info, kopid.eventid, query)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    post,
+    path = "/scim/v2/Groups",
+    request_body=ScimProvisionGroup,
+    responses(
+        (status = 201, content_type="application/scim+json", body=ScimProvisionGroup),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_groups_post"
+)]
+/// Provision a new group.
+async fn scim_provision_groups_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(group): Json<ScimProvisionGroup>,
+) -> Result<(StatusCode, Json<ScimProvisionGroup>), WebError> {
+    state
+        .qe_w_ref
+        .handle_scim_provision_group_create(client_auth_info, kopid.eventid, group)
+        .await
+        .map(|group| (StatusCode::CREATED, Json::from(group)))
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Groups/{id}",
+    responses(
+        (status = 200, content_type="application/scim+json", body=ScimProvisionGroup),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_group_id_get"
+)]
+async fn scim_provision_group_id_get(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<ScimProvisionGroup>, WebError> {
+    state
+        .qe_r_ref
+        .handle_scim_provision_group_get(client_auth_info, kopid.eventid, id)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    put,
+    path = "/scim/v2/Groups/{id}",
+    request_body=ScimProvisionGroup,
+    responses(
+        (status = 200, content_type="application/scim+json", body=ScimProvisionGroup),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_group_id_put"
+)]
+/// Replace the name and members of a group.
+async fn scim_provision_group_id_put(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(group): Json<ScimProvisionGroup>,
+) -> Result<Json<ScimProvisionGroup>, WebError> {
+    state
+        .qe_w_ref
+        .handle_scim_provision_group_replace(client_auth_info, kopid.eventid, id, group)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/scim/v2/Groups/{id}",
+    request_body=ScimPatchRequest,
+    responses(
+        (status = 200, content_type="application/scim+json", body=ScimProvisionGroup),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_group_id_patch"
+)]
+/// Apply a set of changes to a group, such as adding or removing members.
+async fn scim_provision_group_id_patch(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Json(patch): Json<ScimPatchRequest>,
+) -> Result<Json<ScimProvisionGroup>, WebError> {
+    state
+        .qe_w_ref
+        .handle_scim_provision_group_patch(client_auth_info, kopid.eventid, id, patch)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/scim/v2/Groups/{id}",
+    responses(
+        (status = 204, description = "The group was deleted"),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_provision_group_id_delete"
+)]
+/// Deprovision a group.
+async fn scim_provision_group_id_delete(
+    State(state): State<ServerState>,
+    Path(id): Path<Uuid>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<StatusCode, WebError> {
+    state
+        .qe_w_ref
+        .handle_scim_provision_group_delete(client_auth_info, kopid.eventid, id)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(WebError::from)
+}
+
 pub fn route_setup() -> Router<ServerState> {
     Router::new()
         .route(
@@ -479,6 +815,40 @@ pub fn route_setup() -> Router<ServerState> {
         //                                                   {id} is any unique id.
         .route("/scim/v1/Person/:id", get(scim_person_id_get))
         //
+        //  -- Inbound Provisioning
+        //
+        //  These follow the core User and Group schemas so that external
+        //  systems can provision into Kanidm. {id} is the entry uuid.
+        //
+        //  User     /Users           GET, POST              List or create users.
+        //           /Users/{id}      GET, PUT, PATCH,       Retrieve, modify or
+        //                            DELETE                 remove a user.
+        .route(
+            "/scim/v2/Users",
+            get(scim_provision_users_get).post(scim_provision_users_post),
+        )
+        .route(
+            "/scim/v2/Users/:id",
+            get(scim_provision_user_id_get)
+                .put(scim_provision_user_id_put)
+                .patch(scim_provision_user_id_patch)
+                .delete(scim_provision_user_id_delete),
+        )
+        //  Group    /Groups          GET, POST              List or create groups.
+        //           /Groups/{id}     GET, PUT, PATCH,       Retrieve, modify or
+        //                            DELETE                 remove a group.
+        .route(
+            "/scim/v2/Groups",
+            get(scim_provision_groups_get).post(scim_provision_groups_post),
+        )
+        .route(
+            "/scim/v2/Groups/:id",
+            get(scim_provision_group_id_get)
+                .put(scim_provision_group_id_put)
+                .patch(scim_provision_group_id_patch)
+                .delete(scim_provision_group_id_delete),
+        )
+        //
         //  Sync     /Sync            GET                    Retrieve the current
         //                                                   sync state associated
         //                                                   with the authenticated
@@ -0,0 +1,378 @@
+use super::check_idm_admin;
+use crate::https::extractors::{DomainInfo, VerifiedClientInformation};
+use crate::https::middleware::KOpId;
+use crate::https::views::errors::HtmxError;
+use crate::https::views::navbar::NavbarCtx;
+use crate::https::views::Urls;
+use crate::https::ServerState;
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::Uri;
+use axum::response::{ErrorResponse, IntoResponse, Response};
+use axum::{Extension, Form};
+use axum_htmx::{HxPushUrl, HxRequest};
+use futures_util::TryFutureExt;
+use kanidm_proto::attribute::Attribute;
+use kanidm_proto::internal::OperationError;
+use kanidm_proto::scim_v1::client::ScimFilter;
+use kanidm_proto::scim_v1::server::{ScimEffectiveAccess, ScimEntryKanidm, ScimGroup};
+use kanidm_proto::scim_v1::ScimEntryGetQuery;
+use kanidm_proto::v1::{CreateRequest, Entry as ProtoEntry};
+use kanidmd_lib::constants::EntryClass;
+use kanidmd_lib::idm::server::DomainInfoRead;
+use kanidmd_lib::idm::ClientAuthInfo;
+use kanidmd_lib::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const GROUP_ATTRIBUTES: [Attribute; 6] = [
+    Attribute::Uuid,
+    Attribute::Description,
+    Attribute::Name,
+    Attribute::Spn,
+    Attribute::Class,
+    Attribute::Member,
+];
+
+#[derive(Template)]
+#[template(path = "admin/admin_panel_template.html")]
+struct GroupsView {
+    navbar_ctx: NavbarCtx,
+    partial: GroupsPartialView,
+}
+
+#[derive(Template)]
+#[template(path = "admin/admin_groups_partial.html")]
+struct GroupsPartialView {
+    groups: Vec<(ScimGroup, ScimEffectiveAccess)>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/admin_panel_template.html")]
+struct GroupCreateView {
+    navbar_ctx: NavbarCtx,
+    partial: GroupCreatePartialView,
+}
+
+#[derive(Template)]
+#[template(path = "admin/admin_group_create_partial.html")]
+struct GroupCreatePartialView {}
+
+#[derive(Template)]
+#[template(path = "admin/admin_panel_template.html")]
+struct GroupView {
+    partial: GroupViewPartial,
+    navbar_ctx: NavbarCtx,
+}
+
+#[derive(Template)]
+#[template(path = "admin/admin_group_view_partial.html")]
+struct GroupViewPartial {
+    group: ScimGroup,
+    scim_effective_access: ScimEffectiveAccess,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GroupCreateForm {
+    name: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GroupMemberForm {
+    // The name or uuid of the member.
+    member: String,
+}
+
+fn group_filter() -> Filter<FilterInvalid> {
+    filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()))
+}
+
+pub(crate) async fn view_groups_get(
+    State(state): State<ServerState>,
+    HxRequest(is_htmx): HxRequest,
+    Extension(kopid): Extension<KOpId>,
+    DomainInfo(domain_info): DomainInfo,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    let groups = get_groups_info(state, &kopid, client_auth_info, domain_info.clone()).await?;
+    let groups_partial = GroupsPartialView { groups };
+
+    let push_url = HxPushUrl(Uri::from_static("/ui/admin/groups"));
+    Ok(if is_htmx {
+        (push_url, groups_partial).into_response()
+    } else {
+        (
+            push_url,
+            GroupsView {
+                navbar_ctx: NavbarCtx { domain_info },
+                partial: groups_partial,
+            },
+        )
+            .into_response()
+    })
+}
+
+pub(crate) async fn view_group_view_get(
+    State(state): State<ServerState>,
+    HxRequest(is_htmx): HxRequest,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    render_group_view(
+        state,
+        is_htmx,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
+async fn render_group_view(
+    state: ServerState,
+    is_htmx: bool,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+    id: String,
+    domain_info: DomainInfoRead,
+) -> axum::response::Result<Response> {
+    let (group, scim_effective_access) =
+        get_group_info(id, state, &kopid, client_auth_info, domain_info.clone()).await?;
+    let uuid = group.uuid;
+
+    let group_partial = GroupViewPartial {
+        group,
+        scim_effective_access,
+    };
+
+    let path_string = format!("/ui/admin/group/{uuid}/view");
+    let uri = Uri::from_str(path_string.as_str())
+        .map_err(|_| HtmxError::new(&kopid, OperationError::Backend, domain_info.clone()))?;
+    let push_url = HxPushUrl(uri);
+    Ok(if is_htmx {
+        (push_url, group_partial).into_response()
+    } else {
+        (
+            push_url,
+            GroupView {
+                partial: group_partial,
+                navbar_ctx: NavbarCtx { domain_info },
+            },
+        )
+            .into_response()
+    })
+}
+
+pub(crate) async fn view_group_create_get(
+    State(state): State<ServerState>,
+    HxRequest(is_htmx): HxRequest,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    let create_partial = GroupCreatePartialView {};
+
+    let push_url = HxPushUrl(Uri::from_static("/ui/admin/groups/create"));
+    Ok(if is_htmx {
+        (push_url, create_partial).into_response()
+    } else {
+        (
+            push_url,
+            GroupCreateView {
+                navbar_ctx: NavbarCtx { domain_info },
+                partial: create_partial,
+            },
+        )
+            .into_response()
+    })
+}
+
+pub(crate) async fn view_group_create_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<GroupCreateForm>,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    let name = form.name.trim().to_string();
+    let mut attrs = BTreeMap::new();
+    attrs.insert(
+        Attribute::Class.to_string(),
+        vec![EntryClass::Group.into(), EntryClass::Object.into()],
+    );
+    attrs.insert(Attribute::Name.to_string(), vec![name.clone()]);
+    let description = form.description.trim();
+    if !description.is_empty() {
+        attrs.insert(
+            Attribute::Description.to_string(),
+            vec![description.to_string()],
+        );
+    }
+
+    state
+        .qe_w_ref
+        .handle_create(
+            client_auth_info.clone(),
+            CreateRequest {
+                entries: vec![ProtoEntry { attrs }],
+            },
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_group_view(state, true, kopid, client_auth_info, name, domain_info).await
+}
+
+pub(crate) async fn view_group_member_add_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<GroupMemberForm>,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    state
+        .qe_w_ref
+        .handle_appendattribute(
+            client_auth_info.clone(),
+            uuid.to_string(),
+            Attribute::Member.to_string(),
+            vec![form.member.trim().to_string()],
+            group_filter(),
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_group_view(
+        state,
+        true,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
+pub(crate) async fn view_group_member_remove_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<GroupMemberForm>,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    state
+        .qe_w_ref
+        .handle_removeattributevalues(
+            client_auth_info.clone(),
+            uuid.to_string(),
+            Attribute::Member.to_string(),
+            vec![form.member],
+            group_filter(),
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_group_view(
+        state,
+        true,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
+async fn get_group_info(
+    id: String,
+    state: ServerState,
+    kopid: &KOpId,
+    client_auth_info: ClientAuthInfo,
+    domain_info: DomainInfoRead,
+) -> Result<(ScimGroup, ScimEffectiveAccess), ErrorResponse> {
+    let scim_entry: ScimEntryKanidm = state
+        .qe_r_ref
+        .scim_entry_id_get(
+            client_auth_info,
+            kopid.eventid,
+            id,
+            EntryClass::Group,
+            ScimEntryGetQuery {
+                attributes: Some(Vec::from(GROUP_ATTRIBUTES)),
+                ext_access_check: true,
+            },
+        )
+        .map_err(|op_err| HtmxError::new(kopid, op_err, domain_info.clone()))
+        .await?;
+
+    if let Some(group_info) = scimentry_into_groupinfo(scim_entry) {
+        Ok(group_info)
+    } else {
+        Err(HtmxError::new(kopid, OperationError::InvalidState, domain_info.clone()).into())
+    }
+}
+
+async fn get_groups_info(
+    state: ServerState,
+    kopid: &KOpId,
+    client_auth_info: ClientAuthInfo,
+    domain_info: DomainInfoRead,
+) -> Result<Vec<(ScimGroup, ScimEffectiveAccess)>, ErrorResponse> {
+    let filter = ScimFilter::Equal(Attribute::Class.into(), EntryClass::Group.into());
+
+    let base: Vec<ScimEntryKanidm> = state
+        .qe_r_ref
+        .scim_entry_search(
+            client_auth_info,
+            kopid.eventid,
+            filter,
+            ScimEntryGetQuery {
+                attributes: Some(Vec::from(GROUP_ATTRIBUTES)),
+                ext_access_check: true,
+            },
+        )
+        .map_err(|op_err| HtmxError::new(kopid, op_err, domain_info.clone()))
+        .await?;
+
+    let mut groups: Vec<_> = base
+        .into_iter()
+        .filter_map(scimentry_into_groupinfo)
+        .collect();
+
+    groups.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+
+    Ok(groups)
+}
+
+fn scimentry_into_groupinfo(
+    scim_entry: ScimEntryKanidm,
+) -> Option<(ScimGroup, ScimEffectiveAccess)> {
+    let scim_effective_access = scim_entry.ext_access_check.clone()?;
+    let group = ScimGroup::try_from(scim_entry).ok()?;
+
+    Some((group, scim_effective_access))
+}
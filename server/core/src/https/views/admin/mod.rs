@@ -1,19 +1,90 @@
+use crate::https::middleware::KOpId;
+use crate::https::views::errors::HtmxError;
 use crate::https::ServerState;
-use axum::routing::get;
+use axum::response::ErrorResponse;
+use axum::routing::{get, post};
 use axum::Router;
 use axum_htmx::HxRequestGuardLayer;
+use kanidm_proto::internal::OperationError;
+use kanidmd_lib::idm::server::DomainInfoRead;
+use kanidmd_lib::idm::ClientAuthInfo;
 
+mod groups;
 mod persons;
 
 pub fn admin_router() -> Router<ServerState> {
     let unguarded_router = Router::new()
         .route("/persons", get(persons::view_persons_get))
+        .route("/persons/create", get(persons::view_person_create_get))
         .route(
             "/person/:person_uuid/view",
             get(persons::view_person_view_get),
-        );
+        )
+        .route("/groups", get(groups::view_groups_get))
+        .route("/groups/create", get(groups::view_group_create_get))
+        .route("/group/:group_uuid/view", get(groups::view_group_view_get));
 
-    let guarded_router = Router::new().layer(HxRequestGuardLayer::new("/ui"));
+    let guarded_router = Router::new()
+        .route("/persons/create", post(persons::view_person_create_post))
+        .route(
+            "/person/:person_uuid/update",
+            post(persons::view_person_update_post),
+        )
+        .route(
+            "/person/:person_uuid/mail/add",
+            post(persons::view_person_mail_add_post),
+        )
+        .route(
+            "/person/:person_uuid/mail/remove",
+            post(persons::view_person_mail_remove_post),
+        )
+        .route(
+            "/person/:person_uuid/group/add",
+            post(persons::view_person_group_add_post),
+        )
+        .route(
+            "/person/:person_uuid/group/remove",
+            post(persons::view_person_group_remove_post),
+        )
+        .route(
+            "/person/:person_uuid/credential_softlock/clear",
+            post(persons::view_person_softlock_clear_post),
+        )
+        .route(
+            "/person/:person_uuid/impersonate",
+            post(persons::view_person_impersonate_post),
+        )
+        .route("/groups/create", post(groups::view_group_create_post))
+        .route(
+            "/group/:group_uuid/member/add",
+            post(groups::view_group_member_add_post),
+        )
+        .route(
+            "/group/:group_uuid/member/remove",
+            post(groups::view_group_member_remove_post),
+        )
+        .layer(HxRequestGuardLayer::new("/ui"));
 
     Router::new().merge(unguarded_router).merge(guarded_router)
 }
+
+/// The admin pages are only available to members of idm_admins. This is in addition to the
+/// access controls that apply to each change that is made through them.
+async fn check_idm_admin(
+    state: &ServerState,
+    kopid: &KOpId,
+    client_auth_info: &ClientAuthInfo,
+    domain_info: &DomainInfoRead,
+) -> Result<(), ErrorResponse> {
+    let is_idm_admin = state
+        .qe_r_ref
+        .handle_idm_admin_check(client_auth_info.clone(), kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(kopid, op_err, domain_info.clone()))?;
+
+    if is_idm_admin {
+        Ok(())
+    } else {
+        Err(HtmxError::new(kopid, OperationError::AccessDenied, domain_info.clone()).into())
+    }
+}
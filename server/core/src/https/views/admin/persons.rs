@@ -1,5 +1,7 @@
+use super::check_idm_admin;
 use crate::https::extractors::{DomainInfo, VerifiedClientInformation};
 use crate::https::middleware::KOpId;
+use crate::https::views::cookies;
 use crate::https::views::errors::HtmxError;
 use crate::https::views::navbar::NavbarCtx;
 use crate::https::views::Urls;
@@ -8,21 +10,28 @@ use askama::Template;
 use axum::extract::{Path, State};
 use axum::http::Uri;
 use axum::response::{ErrorResponse, IntoResponse, Response};
-use axum::Extension;
-use axum_htmx::{HxPushUrl, HxRequest};
+use axum::{Extension, Form};
+use axum_extra::extract::CookieJar;
+use axum_htmx::{HxLocation, HxPushUrl, HxRequest};
 use futures_util::TryFutureExt;
 use kanidm_proto::attribute::Attribute;
-use kanidm_proto::internal::OperationError;
+use kanidm_proto::internal::{
+    CredentialSoftLockStatus, CredentialUsageDetail, OperationError, COOKIE_BEARER_TOKEN,
+};
 use kanidm_proto::scim_v1::client::ScimFilter;
 use kanidm_proto::scim_v1::server::{ScimEffectiveAccess, ScimEntryKanidm, ScimPerson};
 use kanidm_proto::scim_v1::ScimEntryGetQuery;
+use kanidm_proto::v1::{CreateRequest, Entry as ProtoEntry};
 use kanidmd_lib::constants::EntryClass;
 use kanidmd_lib::idm::server::DomainInfoRead;
 use kanidmd_lib::idm::ClientAuthInfo;
+use kanidmd_lib::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use uuid::Uuid;
 
-const PERSON_ATTRIBUTES: [Attribute; 9] = [
+const PERSON_ATTRIBUTES: [Attribute; 11] = [
     Attribute::Uuid,
     Attribute::Description,
     Attribute::Name,
@@ -32,6 +41,8 @@ const PERSON_ATTRIBUTES: [Attribute; 9] = [
     Attribute::Class,
     Attribute::EntryManagedBy,
     Attribute::DirectMemberOf,
+    Attribute::AccountValidFrom,
+    Attribute::AccountExpire,
 ];
 
 #[derive(Template)]
@@ -47,6 +58,17 @@ struct PersonsPartialView {
     persons: Vec<(ScimPerson, ScimEffectiveAccess)>,
 }
 
+#[derive(Template)]
+#[template(path = "admin/admin_panel_template.html")]
+struct PersonCreateView {
+    navbar_ctx: NavbarCtx,
+    partial: PersonCreatePartialView,
+}
+
+#[derive(Template)]
+#[template(path = "admin/admin_person_create_partial.html")]
+struct PersonCreatePartialView {}
+
 #[derive(Template)]
 #[template(path = "admin/admin_panel_template.html")]
 struct PersonView {
@@ -59,6 +81,42 @@ struct PersonView {
 struct PersonViewPartial {
     person: ScimPerson,
     scim_effective_access: ScimEffectiveAccess,
+    credential_usage: Vec<CredentialUsageDetail>,
+    credential_softlock: Option<CredentialSoftLockStatus>,
+    // If the account is currently within its validity window.
+    account_valid: bool,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PersonCreateForm {
+    name: String,
+    displayname: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PersonUpdateForm {
+    displayname: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PersonMailForm {
+    mail: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PersonGroupForm {
+    // The name or uuid of the group.
+    group: String,
+}
+
+fn person_filter() -> Filter<FilterInvalid> {
+    filter_all!(f_eq(Attribute::Class, EntryClass::Person.into()))
+}
+
+fn group_filter() -> Filter<FilterInvalid> {
+    filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()))
 }
 
 pub(crate) async fn view_person_view_get(
@@ -69,11 +127,60 @@ pub(crate) async fn view_person_view_get(
     Path(uuid): Path<Uuid>,
     DomainInfo(domain_info): DomainInfo,
 ) -> axum::response::Result<Response> {
-    let (person, scim_effective_access) =
-        get_person_info(uuid, state, &kopid, client_auth_info, domain_info.clone()).await?;
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    render_person_view(
+        state,
+        is_htmx,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
+async fn render_person_view(
+    state: ServerState,
+    is_htmx: bool,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+    id: String,
+    domain_info: DomainInfoRead,
+) -> axum::response::Result<Response> {
+    let (person, scim_effective_access) = get_person_info(
+        id,
+        state.clone(),
+        &kopid,
+        client_auth_info.clone(),
+        domain_info.clone(),
+    )
+    .await?;
+    let uuid = person.uuid;
+
+    // Credential usage is only shown if the viewer is able to read the credentials.
+    let credential_usage = state
+        .qe_r_ref
+        .handle_idmcredentialstatus(client_auth_info.clone(), uuid.to_string(), kopid.eventid)
+        .await
+        .map(|status| status.usage)
+        .unwrap_or_default();
+
+    // Likewise the softlock is only shown if the viewer is able to clear it.
+    let credential_softlock = state
+        .qe_r_ref
+        .handle_idmcredentialsoftlockstatus(client_auth_info, uuid.to_string(), kopid.eventid)
+        .await
+        .ok();
+
+    let account_valid = person.is_valid_at(time::OffsetDateTime::now_utc());
+
     let person_partial = PersonViewPartial {
         person,
         scim_effective_access,
+        credential_usage,
+        credential_softlock,
+        account_valid,
     };
 
     let path_string = format!("/ui/admin/person/{uuid}/view");
@@ -94,6 +201,347 @@ pub(crate) async fn view_person_view_get(
     })
 }
 
+pub(crate) async fn view_person_softlock_clear_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    state
+        .qe_r_ref
+        .handle_idmcredentialsoftlockclear(
+            client_auth_info.clone(),
+            uuid.to_string(),
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_person_view(
+        state,
+        true,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
+/// Replace the session of the viewer with a read only session that acts as this person. The
+/// viewer must be a member of idm_impersonation_admins, and will need to log in again once the
+/// impersonation ends.
+pub(crate) async fn view_person_impersonate_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    let token = state
+        .qe_w_ref
+        .handle_impersonate_session(client_auth_info, uuid.to_string(), None, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    // The token has its own expiry, and carries its own signature.
+    let mut bearer_cookie = cookies::make_unsigned(&state, COOKIE_BEARER_TOKEN, token);
+    bearer_cookie.make_permanent();
+    let jar = jar.add(bearer_cookie);
+
+    Ok((
+        jar,
+        HxLocation::from(Uri::from_static(Urls::Apps.as_ref())),
+        "",
+    )
+        .into_response())
+}
+
+pub(crate) async fn view_person_create_get(
+    State(state): State<ServerState>,
+    HxRequest(is_htmx): HxRequest,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    let create_partial = PersonCreatePartialView {};
+
+    let push_url = HxPushUrl(Uri::from_static("/ui/admin/persons/create"));
+    Ok(if is_htmx {
+        (push_url, create_partial).into_response()
+    } else {
+        (
+            push_url,
+            PersonCreateView {
+                navbar_ctx: NavbarCtx { domain_info },
+                partial: create_partial,
+            },
+        )
+            .into_response()
+    })
+}
+
+pub(crate) async fn view_person_create_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<PersonCreateForm>,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    let name = form.name.trim().to_string();
+    let mut attrs = BTreeMap::new();
+    attrs.insert(
+        Attribute::Class.to_string(),
+        vec![
+            EntryClass::Person.into(),
+            EntryClass::Account.into(),
+            EntryClass::Object.into(),
+        ],
+    );
+    attrs.insert(Attribute::Name.to_string(), vec![name.clone()]);
+    attrs.insert(
+        Attribute::DisplayName.to_string(),
+        vec![form.displayname.trim().to_string()],
+    );
+
+    state
+        .qe_w_ref
+        .handle_create(
+            client_auth_info.clone(),
+            CreateRequest {
+                entries: vec![ProtoEntry { attrs }],
+            },
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_person_view(state, true, kopid, client_auth_info, name, domain_info).await
+}
+
+pub(crate) async fn view_person_update_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<PersonUpdateForm>,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    let (person, _) = get_person_info(
+        uuid.to_string(),
+        state.clone(),
+        &kopid,
+        client_auth_info.clone(),
+        domain_info.clone(),
+    )
+    .await?;
+
+    // Only attributes that were changed are written, so that an admin who may only modify
+    // some attributes can still save the form.
+    let displayname = form.displayname.trim();
+    if displayname != person.displayname {
+        state
+            .qe_w_ref
+            .handle_setattribute(
+                client_auth_info.clone(),
+                uuid.to_string(),
+                Attribute::DisplayName.to_string(),
+                vec![displayname.to_string()],
+                person_filter(),
+                kopid.eventid,
+            )
+            .await
+            .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+    }
+
+    let description = form.description.trim();
+    if Some(description) != person.description.as_deref() {
+        let result = if description.is_empty() {
+            state
+                .qe_w_ref
+                .handle_purgeattribute(
+                    client_auth_info.clone(),
+                    uuid.to_string(),
+                    Attribute::Description.to_string(),
+                    person_filter(),
+                    kopid.eventid,
+                )
+                .await
+        } else {
+            state
+                .qe_w_ref
+                .handle_setattribute(
+                    client_auth_info.clone(),
+                    uuid.to_string(),
+                    Attribute::Description.to_string(),
+                    vec![description.to_string()],
+                    person_filter(),
+                    kopid.eventid,
+                )
+                .await
+        };
+        result.map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+    }
+
+    render_person_view(
+        state,
+        true,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
+pub(crate) async fn view_person_mail_add_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<PersonMailForm>,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    state
+        .qe_w_ref
+        .handle_appendattribute(
+            client_auth_info.clone(),
+            uuid.to_string(),
+            Attribute::Mail.to_string(),
+            vec![form.mail.trim().to_string()],
+            person_filter(),
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_person_view(
+        state,
+        true,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
+pub(crate) async fn view_person_mail_remove_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<PersonMailForm>,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    state
+        .qe_w_ref
+        .handle_removeattributevalues(
+            client_auth_info.clone(),
+            uuid.to_string(),
+            Attribute::Mail.to_string(),
+            vec![form.mail],
+            person_filter(),
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_person_view(
+        state,
+        true,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
+pub(crate) async fn view_person_group_add_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<PersonGroupForm>,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    state
+        .qe_w_ref
+        .handle_appendattribute(
+            client_auth_info.clone(),
+            form.group.trim().to_string(),
+            Attribute::Member.to_string(),
+            vec![uuid.to_string()],
+            group_filter(),
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_person_view(
+        state,
+        true,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
+pub(crate) async fn view_person_group_remove_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Path(uuid): Path<Uuid>,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<PersonGroupForm>,
+) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
+    state
+        .qe_w_ref
+        .handle_removeattributevalues(
+            client_auth_info.clone(),
+            form.group,
+            Attribute::Member.to_string(),
+            vec![uuid.to_string()],
+            group_filter(),
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_person_view(
+        state,
+        true,
+        kopid,
+        client_auth_info,
+        uuid.to_string(),
+        domain_info,
+    )
+    .await
+}
+
 pub(crate) async fn view_persons_get(
     State(state): State<ServerState>,
     HxRequest(is_htmx): HxRequest,
@@ -101,6 +549,8 @@ pub(crate) async fn view_persons_get(
     DomainInfo(domain_info): DomainInfo,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
 ) -> axum::response::Result<Response> {
+    check_idm_admin(&state, &kopid, &client_auth_info, &domain_info).await?;
+
     let persons = get_persons_info(state, &kopid, client_auth_info, domain_info.clone()).await?;
     let persons_partial = PersonsPartialView { persons };
 
@@ -120,7 +570,7 @@ pub(crate) async fn view_persons_get(
 }
 
 async fn get_person_info(
-    uuid: Uuid,
+    id: String,
     state: ServerState,
     kopid: &KOpId,
     client_auth_info: ClientAuthInfo,
@@ -131,7 +581,7 @@ async fn get_person_info(
         .scim_entry_id_get(
             client_auth_info.clone(),
             kopid.eventid,
-            uuid.to_string(),
+            id,
             EntryClass::Person,
             ScimEntryGetQuery {
                 attributes: Some(Vec::from(PERSON_ATTRIBUTES)),
@@ -0,0 +1,191 @@
+use askama::Template;
+use askama_axum::IntoResponse;
+
+use axum::extract::State;
+use axum::response::Response;
+use axum::{Extension, Form};
+
+use axum_extra::extract::CookieJar;
+use axum_htmx::HxRequest;
+use kanidm_proto::internal::{ApiTokenDetail, ApiTokenPurpose, OperationError, UserAuthToken};
+use kanidmd_lib::idm::server::DomainInfoRead;
+use kanidmd_lib::idm::ClientAuthInfo;
+use serde::Deserialize;
+use time::format_description::well_known::Iso8601;
+use uuid::Uuid;
+
+use super::constants::{ProfileMenuItems, Urls};
+use super::errors::HtmxError;
+use super::login::{LoginDisplayCtx, Reauth, ReauthPurpose};
+use super::navbar::NavbarCtx;
+use crate::https::extractors::{DomainInfo, VerifiedClientInformation};
+use crate::https::middleware::KOpId;
+use crate::https::ServerState;
+
+#[derive(Template)]
+#[template(path = "user_settings.html")]
+struct ProfileView {
+    navbar_ctx: NavbarCtx,
+    profile_partial: ApiTokensPartialView,
+}
+
+#[derive(Template)]
+#[template(path = "user_settings_api_tokens_partial.html")]
+struct ApiTokensPartialView {
+    menu_active_item: ProfileMenuItems,
+    allowed: bool,
+    tokens: Vec<ApiTokenDetail>,
+    // The newly issued token. This is only ever displayed once.
+    new_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ApiTokenCreateForm {
+    label: String,
+    // The date the token expires, an empty value means it never expires.
+    #[serde(default)]
+    expiry: String,
+    // Checkboxes are only submitted when they are checked.
+    #[serde(default)]
+    read_write: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ApiTokenRevokeForm {
+    token_id: Uuid,
+}
+
+pub(crate) async fn view_api_tokens_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    HxRequest(hx_request): HxRequest,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+) -> axum::response::Result<Response> {
+    let uat: UserAuthToken = state
+        .qe_r_ref
+        .handle_whoami_uat(client_auth_info.clone(), kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    let time = time::OffsetDateTime::now_utc() + time::Duration::new(60, 0);
+    let can_rw = uat.purpose_readwrite_active(time);
+
+    // Tokens can only be issued or revoked with an elevated session, so request a re-auth.
+    if !can_rw {
+        let display_ctx = LoginDisplayCtx {
+            domain_info,
+            locale: kopid.locale,
+            oauth2: None,
+            reauth: Some(Reauth {
+                username: uat.spn,
+                purpose: ReauthPurpose::ProfileSettings,
+            }),
+            error: None,
+        };
+
+        return Ok(super::login::view_step_up_get(
+            state,
+            client_auth_info,
+            kopid,
+            jar,
+            hx_request,
+            Urls::ApiTokens.as_ref(),
+            display_ctx,
+        )
+        .await);
+    }
+
+    let self_tokens = state
+        .qe_r_ref
+        .handle_list_self_api_tokens(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    Ok(ProfileView {
+        navbar_ctx: NavbarCtx { domain_info },
+
+        profile_partial: ApiTokensPartialView {
+            menu_active_item: ProfileMenuItems::ApiTokens,
+            allowed: self_tokens.allowed,
+            tokens: self_tokens.tokens,
+            new_token: None,
+        },
+    }
+    .into_response())
+}
+
+pub(crate) async fn view_api_token_create_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<ApiTokenCreateForm>,
+) -> axum::response::Result<Response> {
+    let expiry = match form.expiry.trim() {
+        "" => None,
+        expiry => {
+            let date = time::Date::parse(expiry, &Iso8601::DATE).map_err(|_| {
+                HtmxError::new(
+                    &kopid,
+                    OperationError::InvalidRequestState,
+                    domain_info.clone(),
+                )
+            })?;
+            Some(date.midnight().assume_utc())
+        }
+    };
+
+    let new_token = state
+        .qe_w_ref
+        .handle_self_api_token_generate(
+            client_auth_info.clone(),
+            form.label,
+            expiry,
+            form.read_write.is_some(),
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_api_tokens_partial(state, kopid, client_auth_info, domain_info, Some(new_token)).await
+}
+
+pub(crate) async fn view_api_token_revoke_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<ApiTokenRevokeForm>,
+) -> axum::response::Result<Response> {
+    state
+        .qe_w_ref
+        .handle_self_api_token_destroy(client_auth_info.clone(), form.token_id, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_api_tokens_partial(state, kopid, client_auth_info, domain_info, None).await
+}
+
+async fn render_api_tokens_partial(
+    state: ServerState,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+    domain_info: DomainInfoRead,
+    new_token: Option<String>,
+) -> axum::response::Result<Response> {
+    let self_tokens = state
+        .qe_r_ref
+        .handle_list_self_api_tokens(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info))?;
+
+    Ok(ApiTokensPartialView {
+        menu_active_item: ProfileMenuItems::ApiTokens,
+        allowed: self_tokens.allowed,
+        tokens: self_tokens.tokens,
+        new_token,
+    }
+    .into_response())
+}
@@ -0,0 +1,179 @@
+use askama::Template;
+use askama_axum::IntoResponse;
+
+use axum::extract::State;
+use axum::response::Response;
+use axum::{Extension, Form};
+
+use axum_extra::extract::CookieJar;
+use axum_htmx::HxRequest;
+use kanidm_proto::internal::{ApplicationPassword, UserAuthToken};
+use kanidmd_lib::idm::server::DomainInfoRead;
+use kanidmd_lib::idm::ClientAuthInfo;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::constants::{ProfileMenuItems, Urls};
+use super::errors::HtmxError;
+use super::login::{LoginDisplayCtx, Reauth, ReauthPurpose};
+use super::navbar::NavbarCtx;
+use crate::https::extractors::{DomainInfo, VerifiedClientInformation};
+use crate::https::middleware::KOpId;
+use crate::https::ServerState;
+
+#[derive(Template)]
+#[template(path = "user_settings.html")]
+struct ProfileView {
+    navbar_ctx: NavbarCtx,
+    profile_partial: AppPasswordsPartialView,
+}
+
+#[derive(Template)]
+#[template(path = "user_settings_app_passwords_partial.html")]
+struct AppPasswordsPartialView {
+    menu_active_item: ProfileMenuItems,
+    app_pwds: Vec<ApplicationPassword>,
+    // The newly generated password. This is only ever displayed once.
+    new_password: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AppPasswordCreateForm {
+    application: String,
+    label: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AppPasswordRevokeForm {
+    password_id: Uuid,
+}
+
+pub(crate) async fn view_app_passwords_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    HxRequest(hx_request): HxRequest,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+) -> axum::response::Result<Response> {
+    let uat: UserAuthToken = state
+        .qe_r_ref
+        .handle_whoami_uat(client_auth_info.clone(), kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    let time = time::OffsetDateTime::now_utc() + time::Duration::new(60, 0);
+    let can_rw = uat.purpose_readwrite_active(time);
+
+    // Passwords can only be generated or revoked with an elevated session, so request a
+    // re-auth.
+    if !can_rw {
+        let display_ctx = LoginDisplayCtx {
+            domain_info,
+            locale: kopid.locale,
+            oauth2: None,
+            reauth: Some(Reauth {
+                username: uat.spn,
+                purpose: ReauthPurpose::ProfileSettings,
+            }),
+            error: None,
+        };
+
+        return Ok(super::login::view_step_up_get(
+            state,
+            client_auth_info,
+            kopid,
+            jar,
+            hx_request,
+            Urls::AppPasswords.as_ref(),
+            display_ctx,
+        )
+        .await);
+    }
+
+    let app_pwds = state
+        .qe_r_ref
+        .handle_list_self_application_passwords(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    Ok(ProfileView {
+        navbar_ctx: NavbarCtx { domain_info },
+
+        profile_partial: AppPasswordsPartialView {
+            menu_active_item: ProfileMenuItems::AppPasswords,
+            app_pwds,
+            new_password: None,
+        },
+    }
+    .into_response())
+}
+
+pub(crate) async fn view_app_password_create_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<AppPasswordCreateForm>,
+) -> axum::response::Result<Response> {
+    let new_password = state
+        .qe_w_ref
+        .handle_self_application_password_generate(
+            client_auth_info.clone(),
+            form.application,
+            form.label,
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_app_passwords_partial(
+        state,
+        kopid,
+        client_auth_info,
+        domain_info,
+        Some(new_password),
+    )
+    .await
+}
+
+pub(crate) async fn view_app_password_revoke_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<AppPasswordRevokeForm>,
+) -> axum::response::Result<Response> {
+    state
+        .qe_w_ref
+        .handle_self_application_password_revoke(
+            client_auth_info.clone(),
+            form.password_id,
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_app_passwords_partial(state, kopid, client_auth_info, domain_info, None).await
+}
+
+async fn render_app_passwords_partial(
+    state: ServerState,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+    domain_info: DomainInfoRead,
+    new_password: Option<String>,
+) -> axum::response::Result<Response> {
+    let app_pwds = state
+        .qe_r_ref
+        .handle_list_self_application_passwords(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info))?;
+
+    Ok(AppPasswordsPartialView {
+        menu_active_item: ProfileMenuItems::AppPasswords,
+        app_pwds,
+        new_password,
+    }
+    .into_response())
+}
@@ -0,0 +1,99 @@
+use askama::Template;
+use askama_axum::IntoResponse;
+
+use axum::extract::State;
+use axum::response::Response;
+use axum::{Extension, Form};
+
+use kanidm_proto::internal::Oauth2Consent;
+use kanidmd_lib::idm::server::DomainInfoRead;
+use kanidmd_lib::idm::ClientAuthInfo;
+use serde::Deserialize;
+
+use super::constants::{ProfileMenuItems, Urls};
+use super::errors::HtmxError;
+use super::navbar::NavbarCtx;
+use crate::https::extractors::{DomainInfo, VerifiedClientInformation};
+use crate::https::middleware::KOpId;
+use crate::https::ServerState;
+
+#[derive(Template)]
+#[template(path = "user_settings.html")]
+struct ProfileView {
+    navbar_ctx: NavbarCtx,
+    profile_partial: ConsentsPartialView,
+}
+
+#[derive(Template)]
+#[template(path = "user_settings_consents_partial.html")]
+struct ConsentsPartialView {
+    menu_active_item: ProfileMenuItems,
+    consents: Vec<Oauth2Consent>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ConsentRevokeForm {
+    client_name: String,
+}
+
+pub(crate) async fn view_consents_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+) -> axum::response::Result<Response> {
+    let consents = state
+        .qe_r_ref
+        .handle_list_self_oauth2_consents(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    Ok(ProfileView {
+        navbar_ctx: NavbarCtx { domain_info },
+
+        profile_partial: ConsentsPartialView {
+            menu_active_item: ProfileMenuItems::Consents,
+            consents,
+        },
+    }
+    .into_response())
+}
+
+pub(crate) async fn view_consent_revoke_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<ConsentRevokeForm>,
+) -> axum::response::Result<Response> {
+    state
+        .qe_w_ref
+        .handle_self_oauth2_consent_revoke(
+            client_auth_info.clone(),
+            form.client_name,
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_consents_partial(state, kopid, client_auth_info, domain_info).await
+}
+
+async fn render_consents_partial(
+    state: ServerState,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+    domain_info: DomainInfoRead,
+) -> axum::response::Result<Response> {
+    let consents = state
+        .qe_r_ref
+        .handle_list_self_oauth2_consents(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info))?;
+
+    Ok(ConsentsPartialView {
+        menu_active_item: ProfileMenuItems::Consents,
+        consents,
+    }
+    .into_response())
+}
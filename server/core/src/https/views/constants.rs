@@ -7,6 +7,11 @@ pub(crate) enum ProfileMenuItems {
     Credentials,
     EnrolDevice,
     UnixPassword,
+    ApiTokens,
+    AppPasswords,
+    Consents,
+    Sessions,
+    GroupRequests,
 }
 
 pub(crate) enum UiMessage {
@@ -22,12 +27,20 @@ impl std::fmt::Display for UiMessage {
 }
 
 pub(crate) enum Urls {
+    AccountRecovery,
+    ApiTokens,
+    AppPasswords,
     Apps,
+    Consents,
     CredReset,
     EnrolDevice,
+    GroupRequests,
     Profile,
+    ProfileUnlock,
+    Sessions,
     UpdateCredentials,
     Oauth2Resume,
+    Saml2Resume,
     Login,
     Ui,
     WellKnownChangePassword,
@@ -36,12 +49,20 @@ pub(crate) enum Urls {
 impl AsRef<str> for Urls {
     fn as_ref(&self) -> &str {
         match self {
+            Self::AccountRecovery => "/ui/recover",
+            Self::ApiTokens => "/ui/api_tokens",
+            Self::AppPasswords => "/ui/app_passwords",
             Self::Apps => "/ui/apps",
+            Self::Consents => "/ui/consents",
             Self::CredReset => "/ui/reset",
             Self::EnrolDevice => "/ui/enrol",
+            Self::GroupRequests => "/ui/profile/group_requests",
             Self::Profile => "/ui/profile",
+            Self::ProfileUnlock => "/ui/profile/unlock",
+            Self::Sessions => "/ui/profile/sessions",
             Self::UpdateCredentials => "/ui/update_credentials",
             Self::Oauth2Resume => "/ui/oauth2/resume",
+            Self::Saml2Resume => "/ui/saml2/resume",
             Self::Login => "/ui/login",
             Self::Ui => "/ui",
             Self::WellKnownChangePassword => "/.well-known/change-password",
@@ -6,10 +6,9 @@ use axum::response::Response;
 use axum::Extension;
 
 use axum_extra::extract::CookieJar;
+use axum_htmx::HxRequest;
 use kanidm_proto::internal::UserAuthToken;
 
-use qrcode::render::svg;
-use qrcode::QrCode;
 use url::Url;
 
 use std::time::Duration;
@@ -21,6 +20,7 @@ use crate::https::middleware::KOpId;
 use crate::https::views::constants::ProfileMenuItems;
 use crate::https::views::errors::HtmxError;
 use crate::https::views::login::{LoginDisplayCtx, Reauth, ReauthPurpose};
+use crate::https::views::render_qr_code_svg;
 use crate::https::ServerState;
 
 #[derive(Template)]
@@ -42,6 +42,7 @@ pub(crate) struct EnrolDeviceView {
 pub(crate) async fn view_enrol_get(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
+    HxRequest(hx_request): HxRequest,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     DomainInfo(domain_info): DomainInfo,
     jar: CookieJar,
@@ -59,6 +60,7 @@ pub(crate) async fn view_enrol_get(
     if !can_rw {
         let display_ctx = LoginDisplayCtx {
             domain_info,
+            locale: kopid.locale,
             oauth2: None,
             reauth: Some(Reauth {
                 username: uat.spn,
@@ -67,11 +69,12 @@ pub(crate) async fn view_enrol_get(
             error: None,
         };
 
-        return Ok(super::login::view_reauth_get(
+        return Ok(super::login::view_step_up_get(
             state,
             client_auth_info,
             kopid,
             jar,
+            hx_request,
             Urls::EnrolDevice.as_ref(),
             display_ctx,
         )
@@ -84,6 +87,8 @@ pub(crate) async fn view_enrol_get(
             client_auth_info,
             uat.spn,
             Some(Duration::from_secs(900)),
+            None,
+            false,
             kopid.eventid,
         )
         .await
@@ -95,13 +100,8 @@ pub(crate) async fn view_enrol_get(
     uri.set_path(Urls::CredReset.as_ref());
     uri.set_query(Some(format!("token={secret}").as_str()));
 
-    let qr_code_svg = match QrCode::new(uri.as_str()) {
-        Ok(qr) => qr.render::<svg::Color>().build(),
-        Err(qr_err) => {
-            error!("Failed to create TOTP QR code: {qr_err}");
-            "QR Code Generation Failed".to_string()
-        }
-    };
+    let qr_code_svg =
+        render_qr_code_svg(uri.as_str()).unwrap_or_else(|| "QR Code Generation Failed".to_string());
 
     Ok(ProfileView {
         navbar_ctx: NavbarCtx { domain_info },
@@ -3,7 +3,6 @@ use axum::response::{IntoResponse, Redirect, Response};
 use axum_htmx::{HxEvent, HxResponseTrigger, HxReswap, HxRetarget, SwapOption};
 use kanidmd_lib::idm::server::DomainInfoRead;
 use utoipa::ToSchema;
-use uuid::Uuid;
 
 use kanidm_proto::internal::OperationError;
 
@@ -22,12 +21,12 @@ use crate::https::views::{ErrorToastPartial, UnrecoverableErrorView};
 #[derive(Debug, ToSchema)]
 pub(crate) enum HtmxError {
     /// Something went wrong when doing things.
-    OperationError(Uuid, OperationError, DomainInfoRead),
+    OperationError(KOpId, OperationError, DomainInfoRead),
 }
 
 impl HtmxError {
     pub(crate) fn new(kopid: &KOpId, operr: OperationError, domain_info: DomainInfoRead) -> Self {
-        HtmxError::OperationError(kopid.eventid, operr, domain_info)
+        HtmxError::OperationError(kopid.clone(), operr, domain_info)
     }
 }
 
@@ -52,7 +51,8 @@ impl IntoResponse for HtmxError {
                                 StatusCode::FORBIDDEN,
                                 ErrorToastPartial {
                                     err_code: inner,
-                                    operation_id: kopid,
+                                    operation_id: kopid.eventid,
+                                    locale: kopid.locale,
                                 },
                             )
                                 .into_response(),
@@ -74,7 +74,8 @@ impl IntoResponse for HtmxError {
                         HxReswap(SwapOption::OuterHtml),
                         UnrecoverableErrorView {
                             err_code: inner,
-                            operation_id: kopid,
+                            operation_id: kopid.eventid,
+                            locale: kopid.locale,
                             domain_info,
                         },
                     )
@@ -84,3 +85,82 @@ impl IntoResponse for HtmxError {
         }
     }
 }
+
+/// A form field that failed validation, and the reason that it failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FieldError {
+    /// The id of the input that failed validation, so that the error summary can link to it.
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// The validation failures of a submitted form. These are rendered as an error summary at the
+/// top of the form, as well as next to each field, with the `form_errors.html` macros.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub(crate) fn push(&mut self, field: &'static str, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field,
+            message: message.into(),
+        });
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &FieldError> {
+        self.0.iter()
+    }
+
+    pub(crate) fn contains(&self, field: &str) -> bool {
+        self.0.iter().any(|e| e.field == field)
+    }
+
+    /// The messages for a single field, in the order they were added.
+    pub(crate) fn messages(&self, field: &str) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|e| e.field == field)
+            .map(|e| e.message.as_str())
+            .collect()
+    }
+}
+
+impl<S: Into<String>> FromIterator<(&'static str, S)> for FieldErrors {
+    fn from_iter<I: IntoIterator<Item = (&'static str, S)>>(iter: I) -> Self {
+        let mut errors = FieldErrors::default();
+        for (field, message) in iter {
+            errors.push(field, message);
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FieldErrors;
+
+    #[test]
+    fn test_field_errors() {
+        let errors: FieldErrors = [
+            ("new-password", "Too short"),
+            ("new-password-check", "Passwords don't match"),
+            ("new-password", "Too common"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert!(!errors.is_empty());
+        assert!(errors.contains("new-password-check"));
+        assert!(!errors.contains("username"));
+        assert_eq!(
+            errors.messages("new-password"),
+            vec!["Too short", "Too common"]
+        );
+        assert_eq!(errors.iter().count(), 3);
+        assert!(FieldErrors::default().is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+use askama::Template;
+use askama_axum::IntoResponse;
+
+use axum::extract::State;
+use axum::response::Response;
+use axum::{Extension, Form};
+
+use axum_extra::extract::CookieJar;
+use axum_htmx::HxRequest;
+use kanidm_proto::internal::{OperationError, UserAuthToken};
+use kanidm_proto::v1::{
+    GroupMembershipRequest, GroupMembershipRequestApprove, GroupMembershipRequestCreate,
+    GroupMembershipRequestStatus,
+};
+use kanidmd_lib::idm::server::DomainInfoRead;
+use kanidmd_lib::idm::ClientAuthInfo;
+use serde::Deserialize;
+use time::format_description::well_known::Iso8601;
+use uuid::Uuid;
+
+use super::constants::{ProfileMenuItems, Urls};
+use super::errors::HtmxError;
+use super::login::{LoginDisplayCtx, Reauth, ReauthPurpose};
+use super::navbar::NavbarCtx;
+use crate::https::extractors::{DomainInfo, VerifiedClientInformation};
+use crate::https::middleware::KOpId;
+use crate::https::ServerState;
+
+#[derive(Template)]
+#[template(path = "user_settings.html")]
+struct ProfileView {
+    navbar_ctx: NavbarCtx,
+    profile_partial: GroupRequestsPartialView,
+}
+
+#[derive(Template)]
+#[template(path = "user_settings_group_requests_partial.html")]
+struct GroupRequestsPartialView {
+    menu_active_item: ProfileMenuItems,
+    awaiting_approval: Vec<GroupMembershipRequest>,
+    requested: Vec<GroupMembershipRequest>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GroupRequestCreateForm {
+    group: String,
+    #[serde(default)]
+    justification: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GroupRequestApproveForm {
+    request_id: Uuid,
+    // The date the membership lapses, an empty value means it never lapses.
+    #[serde(default)]
+    valid_until: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GroupRequestDenyForm {
+    request_id: Uuid,
+}
+
+pub(crate) async fn view_group_requests_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    HxRequest(hx_request): HxRequest,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+) -> axum::response::Result<Response> {
+    let uat: UserAuthToken = state
+        .qe_r_ref
+        .handle_whoami_uat(client_auth_info.clone(), kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    let time = time::OffsetDateTime::now_utc() + time::Duration::new(60, 0);
+    let can_rw = uat.purpose_readwrite_active(time);
+
+    // Requests can only be made or decided with an elevated session, so request a re-auth.
+    if !can_rw {
+        let display_ctx = LoginDisplayCtx {
+            domain_info,
+            locale: kopid.locale,
+            oauth2: None,
+            reauth: Some(Reauth {
+                username: uat.spn,
+                purpose: ReauthPurpose::ProfileSettings,
+            }),
+            error: None,
+        };
+
+        return Ok(super::login::view_step_up_get(
+            state,
+            client_auth_info,
+            kopid,
+            jar,
+            hx_request,
+            Urls::GroupRequests.as_ref(),
+            display_ctx,
+        )
+        .await);
+    }
+
+    let requests = state
+        .qe_r_ref
+        .handle_list_group_membership_requests(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    Ok(ProfileView {
+        navbar_ctx: NavbarCtx { domain_info },
+
+        profile_partial: GroupRequestsPartialView {
+            menu_active_item: ProfileMenuItems::GroupRequests,
+            awaiting_approval: requests.awaiting_approval,
+            requested: requests.requested,
+        },
+    }
+    .into_response())
+}
+
+pub(crate) async fn view_group_request_create_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<GroupRequestCreateForm>,
+) -> axum::response::Result<Response> {
+    let request = GroupMembershipRequestCreate {
+        justification: Some(form.justification),
+    };
+
+    state
+        .qe_w_ref
+        .handle_group_membership_request(
+            client_auth_info.clone(),
+            form.group.trim().to_string(),
+            request,
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_group_requests_partial(state, kopid, client_auth_info, domain_info).await
+}
+
+pub(crate) async fn view_group_request_approve_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<GroupRequestApproveForm>,
+) -> axum::response::Result<Response> {
+    let valid_until = match form.valid_until.trim() {
+        "" => None,
+        valid_until => {
+            let date = time::Date::parse(valid_until, &Iso8601::DATE).map_err(|_| {
+                HtmxError::new(
+                    &kopid,
+                    OperationError::InvalidRequestState,
+                    domain_info.clone(),
+                )
+            })?;
+            Some(date.midnight().assume_utc())
+        }
+    };
+
+    state
+        .qe_w_ref
+        .handle_group_membership_request_decide(
+            client_auth_info.clone(),
+            form.request_id,
+            Some(GroupMembershipRequestApprove { valid_until }),
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_group_requests_partial(state, kopid, client_auth_info, domain_info).await
+}
+
+pub(crate) async fn view_group_request_deny_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<GroupRequestDenyForm>,
+) -> axum::response::Result<Response> {
+    state
+        .qe_w_ref
+        .handle_group_membership_request_decide(
+            client_auth_info.clone(),
+            form.request_id,
+            None,
+            kopid.eventid,
+        )
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_group_requests_partial(state, kopid, client_auth_info, domain_info).await
+}
+
+async fn render_group_requests_partial(
+    state: ServerState,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+    domain_info: DomainInfoRead,
+) -> axum::response::Result<Response> {
+    let requests = state
+        .qe_r_ref
+        .handle_list_group_membership_requests(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info))?;
+
+    Ok(GroupRequestsPartialView {
+        menu_active_item: ProfileMenuItems::GroupRequests,
+        awaiting_approval: requests.awaiting_approval,
+        requested: requests.requested,
+    }
+    .into_response())
+}
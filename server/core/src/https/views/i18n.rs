@@ -0,0 +1,158 @@
+//! Translation of the web views. The catalogs are compiled into the binary, and the language of
+//! each request is chosen from its `Accept-Language` header. Any message that a catalog doesn't
+//! have is shown in English.
+//!
+//! Catalogs are plain text files of `key = value` lines in `server/core/i18n`. Values may
+//! contain `{name}` placeholders that are filled in by [`Locale::format`].
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use kanidm_proto::v1::AuthMech;
+
+type Catalog = BTreeMap<&'static str, &'static str>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+impl Locale {
+    const ALL: [Locale; 2] = [Locale::En, Locale::De];
+
+    /// The language tag of this locale, as used in the `lang` attribute of a page.
+    pub(crate) fn tag(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+        }
+    }
+
+    fn catalog(self) -> &'static Catalog {
+        static EN: OnceLock<Catalog> = OnceLock::new();
+        static DE: OnceLock<Catalog> = OnceLock::new();
+
+        match self {
+            Locale::En => EN.get_or_init(|| parse_catalog(include_str!("../../../i18n/en.txt"))),
+            Locale::De => DE.get_or_init(|| parse_catalog(include_str!("../../../i18n/de.txt"))),
+        }
+    }
+
+    /// Choose the most preferred locale that we have a catalog for from an `Accept-Language`
+    /// header, such as `de-CH, de;q=0.9, en;q=0.8`.
+    pub(crate) fn from_accept_language(header: &str) -> Self {
+        let mut ranges: Vec<(&str, f32)> = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let tag = parts.next().filter(|tag| !tag.is_empty())?;
+                let quality = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+                Some((tag, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+
+        // This is a stable sort, so ranges of equal quality keep the order they were sent in.
+        ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        ranges
+            .into_iter()
+            .find_map(|(tag, _)| {
+                let language = tag.split('-').next().unwrap_or(tag);
+                Locale::ALL
+                    .into_iter()
+                    .find(|locale| locale.tag().eq_ignore_ascii_case(language))
+            })
+            .unwrap_or_default()
+    }
+
+    /// The message for a key. If this locale doesn't have the message the English one is
+    /// used, and if that is missing too, the key itself is shown.
+    pub(crate) fn t<'a>(self, key: &'a str) -> &'a str {
+        self.catalog()
+            .get(key)
+            .or_else(|| Locale::En.catalog().get(key))
+            .copied()
+            .unwrap_or_else(|| {
+                warn!(?key, "Missing translation");
+                key
+            })
+    }
+
+    /// The message for a key, with its `{name}` placeholders replaced by the given values.
+    pub(crate) fn format(self, key: &str, args: &[(&str, &str)]) -> String {
+        args.iter()
+            .fold(self.t(key).to_string(), |message, (name, value)| {
+                message.replace(&format!("{{{name}}}"), value)
+            })
+    }
+
+    pub(crate) fn auth_mech(self, mech: &AuthMech) -> &'static str {
+        self.t(match mech {
+            AuthMech::Anonymous => "login.mech.anonymous",
+            AuthMech::Password => "login.mech.password",
+            AuthMech::PasswordTotp => "login.mech.password_totp",
+            AuthMech::PasswordBackupCode => "login.mech.password_backup_code",
+            AuthMech::PasswordSecurityKey => "login.mech.password_security_key",
+            AuthMech::Passkey => "login.mech.passkey",
+            AuthMech::EmailLink => "login.mech.email_link",
+            AuthMech::Federated => "login.mech.federated",
+            AuthMech::JwtAssertion => "login.mech.jwt_assertion",
+        })
+    }
+}
+
+fn parse_catalog(source: &'static str) -> Catalog {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            Some((key.trim(), value.trim()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Locale;
+
+    #[test]
+    fn test_locale_from_accept_language() {
+        assert_eq!(Locale::from_accept_language(""), Locale::En);
+        assert_eq!(Locale::from_accept_language("de"), Locale::De);
+        assert_eq!(Locale::from_accept_language("de-CH, en;q=0.5"), Locale::De);
+        assert_eq!(Locale::from_accept_language("fr, de;q=0.8"), Locale::De);
+        assert_eq!(
+            Locale::from_accept_language("en;q=0.5, de;q=0.9"),
+            Locale::De
+        );
+        assert_eq!(Locale::from_accept_language("de;q=0, en"), Locale::En);
+        assert_eq!(Locale::from_accept_language("fr, *;q=0.5"), Locale::En);
+    }
+
+    #[test]
+    fn test_locale_catalogs() {
+        let en = Locale::En.catalog();
+        assert!(!en.is_empty());
+
+        // Every message of a catalog must have an English message to fall back from.
+        for locale in Locale::ALL {
+            for key in locale.catalog().keys() {
+                assert!(en.contains_key(key), "{key} is missing from en");
+            }
+        }
+
+        assert_eq!(Locale::De.t("login.username"), "Benutzername");
+        assert_eq!(Locale::De.t("login.missing"), "login.missing");
+        assert_eq!(
+            Locale::En.format("login.oauth2", &[("client", "Test")]),
+            "Authenticate to access Test"
+        );
+    }
+}
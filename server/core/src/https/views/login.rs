@@ -1,31 +1,39 @@
 use super::constants::Urls;
+use super::i18n::Locale;
+use super::login_challenge::{LoginChallenge, LoginChallengeSolution};
+use super::reset::add_cu_cookie;
 use super::{cookies, empty_string_as_none, UnrecoverableErrorView};
-use crate::https::views::errors::HtmxError;
+use crate::https::views::errors::{FieldErrors, HtmxError};
 use crate::https::{
     extractors::{DomainInfo, DomainInfoRead, VerifiedClientInformation},
-    middleware::KOpId,
+    middleware::{
+        rate_limit::{check_account_rate_limit, retry_after_secs},
+        KOpId,
+    },
     ServerState,
 };
 use askama::Template;
 use axum::{
-    extract::State,
+    extract::{Query, State},
+    http::{header, StatusCode},
     response::{IntoResponse, Redirect, Response},
     Extension, Form, Json,
 };
 use axum_extra::extract::cookie::{CookieJar, SameSite};
+use axum_htmx::{HxReswap, HxRetarget, SwapOption};
 use kanidm_proto::internal::{
-    COOKIE_AUTH_SESSION_ID, COOKIE_BEARER_TOKEN, COOKIE_CU_SESSION_TOKEN, COOKIE_OAUTH2_REQ,
-    COOKIE_USERNAME,
+    COOKIE_AUTH_SESSION_ID, COOKIE_BEARER_TOKEN, COOKIE_BOUND_SESSION_ID, COOKIE_CU_SESSION_TOKEN,
+    COOKIE_OAUTH2_REQ, COOKIE_REMEMBER_ME, COOKIE_SAML2_REQ,
 };
 use kanidm_proto::v1::{
     AuthAllowed, AuthCredential, AuthIssueSession, AuthMech, AuthRequest, AuthStep,
+    FederatedProvider,
 };
 use kanidmd_lib::idm::event::AuthResult;
-use kanidmd_lib::idm::AuthState;
+use kanidmd_lib::idm::{AuthDeniedKind, AuthState};
 use kanidmd_lib::prelude::OperationError;
 use kanidmd_lib::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::fmt;
 use std::str::FromStr;
 use webauthn_rs::prelude::PublicKeyCredential;
 
@@ -46,6 +54,25 @@ struct SessionContext {
 
     #[serde(rename = "a", default, skip_serializing_if = "Option::is_none")]
     after_auth_loc: Option<String>,
+
+    /// The mech that was chosen, or that we should choose if it's available.
+    #[serde(rename = "m", default, skip_serializing_if = "Option::is_none")]
+    mech: Option<AuthMech>,
+}
+
+/// Remembers the last user to login with this browser, so that when they return they can
+/// skip the username form and go straight to their preferred mech.
+#[derive(Serialize, Deserialize)]
+struct RememberMe {
+    #[serde(rename = "u")]
+    username: String,
+
+    #[serde(rename = "m", default, skip_serializing_if = "Option::is_none")]
+    mech: Option<AuthMech>,
+}
+
+fn get_remember_me(state: &ServerState, jar: &CookieJar) -> Option<RememberMe> {
+    cookies::get_signed::<RememberMe>(state, jar, COOKIE_REMEMBER_ME)
 }
 
 #[derive(Clone)]
@@ -53,22 +80,35 @@ pub enum ReauthPurpose {
     ProfileSettings,
 }
 
-impl fmt::Display for ReauthPurpose {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl ReauthPurpose {
+    fn message_key(&self) -> &'static str {
         match self {
-            Self::ProfileSettings => write!(f, "Profile and Settings"),
+            Self::ProfileSettings => "login.reauth.profile_settings",
         }
     }
 }
 #[derive(Clone)]
 pub enum LoginError {
     InvalidUsername,
+    RateLimited,
+    ChallengeRequired,
 }
 
-impl fmt::Display for LoginError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl LoginError {
+    /// The id of the input that this error relates to.
+    fn field(&self) -> &'static str {
         match self {
-            Self::InvalidUsername => write!(f, "Invalid username"),
+            Self::InvalidUsername | Self::RateLimited | Self::ChallengeRequired => "username",
+        }
+    }
+}
+
+impl LoginError {
+    fn message_key(&self) -> &'static str {
+        match self {
+            Self::InvalidUsername => "login.error.invalid_username",
+            Self::RateLimited => "login.error.rate_limited",
+            Self::ChallengeRequired => "login.error.challenge_required",
         }
     }
 }
@@ -85,22 +125,67 @@ pub struct Oauth2Ctx {
 #[derive(Clone)]
 pub struct LoginDisplayCtx {
     pub domain_info: DomainInfoRead,
+    pub locale: Locale,
     // We only need this on the first re-auth screen to indicate what we are doing
     pub reauth: Option<Reauth>,
     pub oauth2: Option<Oauth2Ctx>,
     pub error: Option<LoginError>,
 }
 
+impl LoginDisplayCtx {
+    fn field_errors(&self) -> FieldErrors {
+        self.error
+            .iter()
+            .map(|error| {
+                (
+                    error.field(),
+                    self.locale.t(error.message_key()).to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn reauth_message(&self) -> Option<String> {
+        self.reauth.as_ref().map(|reauth| {
+            self.locale.format(
+                "login.reauth",
+                &[
+                    ("username", reauth.username.as_str()),
+                    ("purpose", self.locale.t(reauth.purpose.message_key())),
+                ],
+            )
+        })
+    }
+
+    fn oauth2_message(&self) -> Option<String> {
+        self.oauth2.as_ref().map(|oauth2| {
+            self.locale
+                .format("login.oauth2", &[("client", oauth2.client_name.as_str())])
+        })
+    }
+}
+
 #[derive(Template)]
 #[template(path = "login.html")]
 struct LoginView {
     display_ctx: LoginDisplayCtx,
     username: String,
     remember_me: bool,
+    account_recovery: bool,
+    passkey_autofill: Option<PasskeyAutofill>,
+    /// A proof of work that must be solved before the login can begin.
+    challenge: Option<LoginChallenge>,
+}
+
+/// A passkey challenge for the username field, so that the browser can offer the user's
+/// passkeys for this site as they would a saved username.
+pub struct PasskeyAutofill {
+    chal: String,
+    csp_nonce: String,
 }
 
 pub struct Mech<'a> {
-    name: AuthMech,
+    name: &'static str,
     value: &'a str,
     autofocus: bool,
 }
@@ -112,19 +197,12 @@ struct LoginMechView<'a> {
     mechs: Vec<Mech<'a>>,
 }
 
-#[derive(Default)]
-enum LoginTotpError {
-    #[default]
-    None,
-    Syntax,
-}
-
 #[derive(Template)]
 #[template(path = "login_totp.html")]
 struct LoginTotpView {
     display_ctx: LoginDisplayCtx,
     totp: String,
-    errors: LoginTotpError,
+    field_errors: FieldErrors,
 }
 
 #[derive(Template)]
@@ -140,6 +218,27 @@ struct LoginBackupCodeView {
     display_ctx: LoginDisplayCtx,
 }
 
+/// Without a token this asks the user to check their email. With the token from the link it
+/// asks them to continue, so that the token is only submitted from this site where the auth
+/// session cookie is present, and not by anything that fetches the link ahead of them.
+#[derive(Template)]
+#[template(path = "login_email_link.html")]
+struct LoginEmailLinkView {
+    display_ctx: LoginDisplayCtx,
+    token: Option<String>,
+}
+
+/// Without a callback this lists the upstream identity providers to login with. With the
+/// code and state from the provider it asks the user to continue, for the same reason as
+/// an email link - the auth session cookie isn't sent when the provider redirects back.
+#[derive(Template)]
+#[template(path = "login_federated.html")]
+struct LoginFederatedView {
+    display_ctx: LoginDisplayCtx,
+    providers: Vec<FederatedProvider>,
+    callback: Option<(String, String)>,
+}
+
 #[derive(Template)]
 #[template(path = "login_webauthn.html")]
 struct LoginWebauthnView {
@@ -148,16 +247,37 @@ struct LoginWebauthnView {
     passkey: bool,
     // chal: RequestChallengeResponse,
     chal: String,
+    // Inline scripts must carry the nonce of the content security policy.
+    csp_nonce: String,
 }
 
 #[derive(Template)]
 #[template(path = "login_denied.html")]
 struct LoginDeniedView {
     display_ctx: LoginDisplayCtx,
-    reason: String,
+    message_key: &'static str,
     operation_id: Uuid,
 }
 
+/// The message shown when a login is denied. The reason itself is only logged, since it would
+/// tell someone guessing credentials which of them was incorrect. The operation id lets an
+/// administrator find the reason in the logs.
+fn denied_message_key(kind: AuthDeniedKind) -> &'static str {
+    match kind {
+        AuthDeniedKind::CredentialsIncorrect => "login.denied.credentials_incorrect",
+        AuthDeniedKind::AccountLocked => "login.denied.account_locked",
+        AuthDeniedKind::AccountExpired => "login.denied.account_expired",
+        AuthDeniedKind::PolicyDenied => "login.denied.policy_denied",
+    }
+}
+
+#[derive(Template)]
+#[template(path = "login_step_up_partial.html")]
+struct LoginStepUpPartial {
+    display_ctx: LoginDisplayCtx,
+    return_location: String,
+}
+
 pub async fn view_logout_get(
     State(state): State<ServerState>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
@@ -173,6 +293,7 @@ pub async fn view_logout_get(
         UnrecoverableErrorView {
             err_code,
             operation_id: kopid.eventid,
+            locale: kopid.locale,
             domain_info,
         }
         .into_response()
@@ -182,13 +303,32 @@ pub async fn view_logout_get(
 
     // Always clear cookies even on an error.
     jar = cookies::destroy(jar, COOKIE_BEARER_TOKEN, &state);
+    jar = cookies::destroy(jar, COOKIE_BOUND_SESSION_ID, &state);
     jar = cookies::destroy(jar, COOKIE_OAUTH2_REQ, &state);
+    jar = cookies::destroy(jar, COOKIE_SAML2_REQ, &state);
     jar = cookies::destroy(jar, COOKIE_AUTH_SESSION_ID, &state);
     jar = cookies::destroy(jar, COOKIE_CU_SESSION_TOKEN, &state);
 
     (jar, response).into_response()
 }
 
+/// Forget the remembered user and abandon any in progress authentication so that
+/// a different account can be used.
+pub async fn view_login_forget_get(State(state): State<ServerState>, jar: CookieJar) -> Response {
+    let response = if jar.get(COOKIE_OAUTH2_REQ).is_some() {
+        Redirect::to(Urls::Oauth2Resume.as_ref()).into_response()
+    } else if jar.get(COOKIE_SAML2_REQ).is_some() {
+        Redirect::to(Urls::Saml2Resume.as_ref()).into_response()
+    } else {
+        Redirect::to(Urls::Login.as_ref()).into_response()
+    };
+
+    let jar = cookies::destroy(jar, COOKIE_REMEMBER_ME, &state);
+    let jar = cookies::destroy(jar, COOKIE_AUTH_SESSION_ID, &state);
+
+    (jar, response).into_response()
+}
+
 pub async fn view_reauth_get(
     state: ServerState,
     client_auth_info: ClientAuthInfo,
@@ -227,6 +367,7 @@ pub async fn view_reauth_get(
                         totp: None,
                         remember_me: false,
                         after_auth_loc: Some(return_location.to_string()),
+                        mech: None,
                     };
 
                     match view_login_step(
@@ -245,6 +386,7 @@ pub async fn view_reauth_get(
                         Err(err_code) => UnrecoverableErrorView {
                             err_code,
                             operation_id: kopid.eventid,
+                            locale: kopid.locale,
                             domain_info: display_ctx.clone().domain_info,
                         }
                         .into_response(),
@@ -254,6 +396,7 @@ pub async fn view_reauth_get(
                 Err(err_code) => UnrecoverableErrorView {
                     err_code,
                     operation_id: kopid.eventid,
+                    locale: kopid.locale,
                     domain_info: display_ctx.domain_info,
                 }
                 .into_response(),
@@ -261,9 +404,8 @@ pub async fn view_reauth_get(
         }
         Err(OperationError::NotAuthenticated) | Err(OperationError::SessionExpired) => {
             // cookie jar with remember me.
-
-            let username = cookies::get_unsigned(&jar, COOKIE_USERNAME)
-                .map(String::from)
+            let username = get_remember_me(&state, &jar)
+                .map(|remember_me| remember_me.username)
                 .unwrap_or_default();
 
             let remember_me = !username.is_empty();
@@ -274,6 +416,9 @@ pub async fn view_reauth_get(
                     display_ctx,
                     username,
                     remember_me,
+                    account_recovery: state.qe_w_ref.account_recovery_enabled(),
+                    passkey_autofill: None,
+                    challenge: None,
                 },
             )
                 .into_response()
@@ -281,24 +426,175 @@ pub async fn view_reauth_get(
         Err(err_code) => UnrecoverableErrorView {
             err_code,
             operation_id: kopid.eventid,
+            locale: kopid.locale,
             domain_info: display_ctx.domain_info,
         }
         .into_response(),
     }
 }
 
+/// Request that the user reauthenticates to access a privileged view, returning them to
+/// `return_location` once they have. Within a htmx request we render a step-up partial in
+/// place of the current view, rather than replacing the page with the login view.
+pub async fn view_step_up_get(
+    state: ServerState,
+    client_auth_info: ClientAuthInfo,
+    kopid: KOpId,
+    jar: CookieJar,
+    hx_request: bool,
+    return_location: &str,
+    display_ctx: LoginDisplayCtx,
+) -> Response {
+    if hx_request && display_ctx.reauth.is_some() {
+        (
+            HxRetarget("main".to_string()),
+            HxReswap(SwapOption::OuterHtml),
+            LoginStepUpPartial {
+                display_ctx,
+                return_location: return_location.to_string(),
+            },
+        )
+            .into_response()
+    } else {
+        view_reauth_get(
+            state,
+            client_auth_info,
+            kopid,
+            jar,
+            return_location,
+            display_ctx,
+        )
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoginStepUpForm {
+    return_location: String,
+}
+
+/// Begin a privileged authentication for the current user from the step-up partial.
+pub async fn view_login_step_up_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+    Form(login_step_up_form): Form<LoginStepUpForm>,
+) -> Response {
+    let LoginStepUpForm { return_location } = login_step_up_form;
+
+    // Only return to our own views so that this can't be used as an open redirect.
+    let after_auth_loc = if return_location.starts_with("/ui/") {
+        return_location
+    } else {
+        Urls::Apps.as_ref().to_string()
+    };
+
+    let uat = match state
+        .qe_r_ref
+        .handle_whoami_uat(client_auth_info.clone(), kopid.eventid)
+        .await
+    {
+        Ok(uat) => uat,
+        // The session is no longer valid, so they need to login again.
+        Err(OperationError::NotAuthenticated) | Err(OperationError::SessionExpired) => {
+            return Redirect::to(Urls::Login.as_ref()).into_response()
+        }
+        Err(err_code) => {
+            return UnrecoverableErrorView {
+                err_code,
+                operation_id: kopid.eventid,
+                locale: kopid.locale,
+                domain_info,
+            }
+            .into_response()
+        }
+    };
+
+    let inter = state
+        .qe_r_ref
+        .handle_auth(
+            None,
+            AuthRequest {
+                step: AuthStep::Init2 {
+                    username: uat.spn.clone(),
+                    issue: AuthIssueSession::Cookie,
+                    privileged: true,
+                },
+            },
+            kopid.eventid,
+            client_auth_info.clone(),
+        )
+        .await;
+
+    let session_context = SessionContext {
+        id: None,
+        username: uat.spn.clone(),
+        password: None,
+        totp: None,
+        remember_me: false,
+        after_auth_loc: Some(after_auth_loc),
+        mech: None,
+    };
+
+    let display_ctx = LoginDisplayCtx {
+        domain_info: domain_info.clone(),
+        locale: kopid.locale,
+        oauth2: None,
+        reauth: Some(Reauth {
+            username: uat.spn,
+            purpose: ReauthPurpose::ProfileSettings,
+        }),
+        error: None,
+    };
+
+    match inter {
+        Ok(ar) => {
+            match view_login_step(
+                state,
+                kopid.clone(),
+                jar,
+                ar,
+                client_auth_info,
+                session_context,
+                display_ctx,
+            )
+            .await
+            {
+                Ok(r) => r,
+                Err(err_code) => UnrecoverableErrorView {
+                    err_code,
+                    operation_id: kopid.eventid,
+                    locale: kopid.locale,
+                    domain_info,
+                }
+                .into_response(),
+            }
+        }
+        Err(err_code) => UnrecoverableErrorView {
+            err_code,
+            operation_id: kopid.eventid,
+            locale: kopid.locale,
+            domain_info,
+        }
+        .into_response(),
+    }
+}
+
 pub fn view_oauth2_get(
+    state: &ServerState,
     jar: CookieJar,
     display_ctx: LoginDisplayCtx,
     login_hint: Option<String>,
 ) -> Response {
     let (username, remember_me) = if let Some(login_hint) = login_hint {
         (login_hint, false)
-    } else if let Some(cookie_username) =
+    } else if let Some(remember_me) =
         // cookie jar with remember me.
-        jar.get(COOKIE_USERNAME).map(|c| c.value().to_string())
+        get_remember_me(state, &jar)
     {
-        (cookie_username, true)
+        (remember_me.username, true)
     } else {
         (String::default(), false)
     };
@@ -309,6 +605,9 @@ pub fn view_oauth2_get(
             display_ctx,
             username,
             remember_me,
+            account_recovery: state.qe_w_ref.account_recovery_enabled(),
+            passkey_autofill: None,
+            challenge: None,
         },
     )
         .into_response()
@@ -324,7 +623,7 @@ pub async fn view_index_get(
     // If we are authenticated, redirect to the landing.
     let session_valid_result = state
         .qe_r_ref
-        .handle_auth_valid(client_auth_info, kopid.eventid)
+        .handle_auth_valid(client_auth_info.clone(), kopid.eventid)
         .await;
 
     // No matter what, we always clear the stored oauth2 cookie to prevent
@@ -337,27 +636,58 @@ pub async fn view_index_get(
             (jar, Redirect::to(Urls::Apps.as_ref())).into_response()
         }
         Err(OperationError::NotAuthenticated) | Err(OperationError::SessionExpired) => {
-            // cookie jar with remember me.
-            let username = jar
-                .get(COOKIE_USERNAME)
-                .map(|c| c.value().to_string())
-                .unwrap_or_default();
-
-            let remember_me = !username.is_empty();
-
             let display_ctx = LoginDisplayCtx {
                 domain_info,
+                locale: kopid.locale,
                 oauth2: None,
                 reauth: None,
                 error: None,
             };
 
+            // If we remember this user, skip the username form.
+            if let Some(remember_me) = get_remember_me(&state, &jar) {
+                let session_context = SessionContext {
+                    id: None,
+                    username: remember_me.username,
+                    password: None,
+                    totp: None,
+                    remember_me: true,
+                    after_auth_loc: None,
+                    mech: remember_me.mech,
+                };
+
+                return view_login_begin(
+                    state,
+                    kopid,
+                    jar,
+                    client_auth_info,
+                    session_context,
+                    None,
+                    display_ctx,
+                )
+                .await;
+            }
+
+            // Offer passkeys in the username autofill. If the challenge can't be created the
+            // user can still login with their username.
+            let (jar, passkey_autofill) =
+                match passkey_autofill_challenge(&state, &kopid, jar.clone()).await {
+                    Ok((jar, passkey_autofill)) => (jar, Some(passkey_autofill)),
+                    Err(err) => {
+                        warn!(?err, "Unable to create passkey autofill challenge");
+                        (jar, None)
+                    }
+                };
+
             (
                 jar,
                 LoginView {
                     display_ctx,
-                    username,
-                    remember_me,
+                    username: String::default(),
+                    remember_me: false,
+                    account_recovery: state.qe_w_ref.account_recovery_enabled(),
+                    passkey_autofill,
+                    challenge: None,
                 },
             )
                 .into_response()
@@ -365,12 +695,42 @@ pub async fn view_index_get(
         Err(err_code) => UnrecoverableErrorView {
             err_code,
             operation_id: kopid.eventid,
+            locale: kopid.locale,
             domain_info,
         }
         .into_response(),
     }
 }
 
+/// Create a discoverable passkey challenge, and remember it in the session cookie so that
+/// the passkey the user selects can be checked against it.
+async fn passkey_autofill_challenge(
+    state: &ServerState,
+    kopid: &KOpId,
+    jar: CookieJar,
+) -> Result<(CookieJar, PasskeyAutofill), OperationError> {
+    let (challenge_id, chal) = state
+        .qe_r_ref
+        .handle_auth_discoverable_begin(kopid.eventid)
+        .await?;
+
+    let chal = serde_json::to_string(&chal).map_err(|_| OperationError::SerdeJsonError)?;
+
+    let session_context = SessionContext {
+        id: Some(challenge_id),
+        ..Default::default()
+    };
+    let jar = add_session_cookie(state, jar, &session_context)?;
+
+    Ok((
+        jar,
+        PasskeyAutofill {
+            chal,
+            csp_nonce: kopid.csp_nonce.clone(),
+        },
+    ))
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct LoginBeginForm {
     username: String,
@@ -380,6 +740,10 @@ pub struct LoginBeginForm {
     totp: Option<String>,
     #[serde(default)]
     remember_me: Option<u8>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pow_nonce: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pow_solution: Option<String>,
 }
 
 pub async fn view_login_begin_post(
@@ -395,48 +759,125 @@ pub async fn view_login_begin_post(
         password,
         totp,
         remember_me,
+        pow_nonce,
+        pow_solution,
     } = login_begin_form;
 
     trace!(?remember_me);
 
-    // Init the login.
-    let inter = state // This may change in the future ...
-        .qe_r_ref
-        .handle_auth(
-            None,
-            AuthRequest {
-                step: AuthStep::Init2 {
-                    username: username.clone(),
-                    issue: AuthIssueSession::Cookie,
-                    privileged: false,
-                },
-            },
-            kopid.eventid,
-            client_auth_info.clone(),
-        )
-        .await;
-
     let remember_me = remember_me.is_some();
 
+    // The user has asked us to forget them.
+    let jar = if remember_me {
+        jar
+    } else {
+        cookies::destroy(jar, COOKIE_REMEMBER_ME, &state)
+    };
+
+    let challenge_solution = pow_nonce
+        .zip(pow_solution)
+        .map(|(nonce, solution)| LoginChallengeSolution { nonce, solution });
+
     let session_context = SessionContext {
         id: None,
-        username: username.clone(),
+        username,
         password,
         totp,
         remember_me,
         after_auth_loc: None,
+        mech: None,
     };
 
-    let mut display_ctx = LoginDisplayCtx {
-        domain_info: domain_info.clone(),
+    let display_ctx = LoginDisplayCtx {
+        domain_info,
+        locale: kopid.locale,
         oauth2: None,
         reauth: None,
         error: None,
     };
 
+    view_login_begin(
+        state,
+        kopid,
+        jar,
+        client_auth_info,
+        session_context,
+        challenge_solution,
+        display_ctx,
+    )
+    .await
+}
+
+/// Init the login for the username of the session context.
+async fn view_login_begin(
+    state: ServerState,
+    kopid: KOpId,
+    jar: CookieJar,
+    client_auth_info: ClientAuthInfo,
+    session_context: SessionContext,
+    challenge_solution: Option<LoginChallengeSolution>,
+    mut display_ctx: LoginDisplayCtx,
+) -> Response {
+    if let Err(retry_after) =
+        check_account_rate_limit(&state, &session_context.username, kopid.eventid).await
+    {
+        warn!(username = %session_context.username, "Authentication rate limit reached for account");
+        display_ctx.error = Some(LoginError::RateLimited);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after_secs(retry_after))],
+            jar,
+            LoginView {
+                display_ctx,
+                username: session_context.username,
+                remember_me: session_context.remember_me,
+                account_recovery: state.qe_w_ref.account_recovery_enabled(),
+                passkey_autofill: None,
+                challenge: None,
+            },
+        )
+            .into_response();
+    }
+
+    if let Err(challenge) = state
+        .login_challenges
+        .check(&session_context.username, challenge_solution.as_ref())
+    {
+        display_ctx.error = Some(LoginError::ChallengeRequired);
+        return (
+            jar,
+            LoginView {
+                display_ctx,
+                username: session_context.username,
+                remember_me: session_context.remember_me,
+                account_recovery: state.qe_w_ref.account_recovery_enabled(),
+                passkey_autofill: None,
+                challenge: Some(challenge),
+            },
+        )
+            .into_response();
+    }
+
+    let inter = state // This may change in the future ...
+        .qe_r_ref
+        .handle_auth(
+            None,
+            AuthRequest {
+                step: AuthStep::Init2 {
+                    username: session_context.username.clone(),
+                    issue: AuthIssueSession::Cookie,
+                    privileged: false,
+                },
+            },
+            kopid.eventid,
+            client_auth_info.clone(),
+        )
+        .await;
+
     // Now process the response if ok.
     match inter {
         Ok(ar) => {
+            let domain_info = display_ctx.domain_info.clone();
             match view_login_step(
                 state,
                 kopid.clone(),
@@ -453,6 +894,7 @@ pub async fn view_login_begin_post(
                 Err(err_code) => UnrecoverableErrorView {
                     err_code,
                     operation_id: kopid.eventid,
+                    locale: kopid.locale,
                     domain_info,
                 }
                 .into_response(),
@@ -461,18 +903,27 @@ pub async fn view_login_begin_post(
         // Probably needs to be way nicer on login, especially something like no matching users ...
         Err(err_code) => match err_code {
             OperationError::NoMatchingEntries => {
+                // Don't remember a user that doesn't exist.
+                let jar = cookies::destroy(jar, COOKIE_REMEMBER_ME, &state);
                 display_ctx.error = Some(LoginError::InvalidUsername);
-                LoginView {
-                    display_ctx,
-                    username,
-                    remember_me,
-                }
-                .into_response()
+                (
+                    jar,
+                    LoginView {
+                        display_ctx,
+                        username: session_context.username,
+                        remember_me: session_context.remember_me,
+                        account_recovery: state.qe_w_ref.account_recovery_enabled(),
+                        passkey_autofill: None,
+                        challenge: None,
+                    },
+                )
+                    .into_response()
             }
             _ => UnrecoverableErrorView {
                 err_code,
                 operation_id: kopid.eventid,
-                domain_info,
+                locale: kopid.locale,
+                domain_info: display_ctx.domain_info,
             }
             .into_response(),
         },
@@ -492,7 +943,7 @@ pub async fn view_login_mech_choose_post(
     jar: CookieJar,
     Form(login_mech_form): Form<LoginMechForm>,
 ) -> Response {
-    let session_context =
+    let mut session_context =
         cookies::get_signed::<SessionContext>(&state, &jar, COOKIE_AUTH_SESSION_ID)
             .unwrap_or_default();
 
@@ -500,6 +951,9 @@ pub async fn view_login_mech_choose_post(
 
     let LoginMechForm { mech } = login_mech_form;
 
+    // Remember the choice so that it can be preferred next time.
+    session_context.mech = Some(mech.clone());
+
     let inter = state // This may change in the future ...
         .qe_r_ref
         .handle_auth(
@@ -514,6 +968,7 @@ pub async fn view_login_mech_choose_post(
 
     let display_ctx = LoginDisplayCtx {
         domain_info: domain_info.clone(),
+        locale: kopid.locale,
         oauth2: None,
         reauth: None,
         error: None,
@@ -538,6 +993,7 @@ pub async fn view_login_mech_choose_post(
                 Err(err_code) => UnrecoverableErrorView {
                     err_code,
                     operation_id: kopid.eventid,
+                    locale: kopid.locale,
                     domain_info,
                 }
                 .into_response(),
@@ -547,6 +1003,7 @@ pub async fn view_login_mech_choose_post(
         Err(err_code) => UnrecoverableErrorView {
             err_code,
             operation_id: kopid.eventid,
+            locale: kopid.locale,
             domain_info,
         }
         .into_response(),
@@ -574,15 +1031,21 @@ pub async fn view_login_totp_post(
         Err(_) => {
             let display_ctx = LoginDisplayCtx {
                 domain_info,
+                locale: kopid.locale,
                 oauth2: None,
                 reauth: None,
                 error: None,
             };
             // If not an int, we need to re-render with an error
+            let mut field_errors = FieldErrors::default();
+            field_errors.push(
+                "totp",
+                "The code must only contain numbers, please try again.",
+            );
             return LoginTotpView {
                 display_ctx,
                 totp: String::default(),
-                errors: LoginTotpError::Syntax,
+                field_errors,
             }
             .into_response();
         }
@@ -653,6 +1116,157 @@ pub async fn view_login_backupcode_post(
     credential_step(state, kopid, jar, client_auth_info, auth_cred, domain_info).await
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoginEmailLinkForm {
+    token: String,
+}
+
+pub async fn view_login_email_link_get(
+    Extension(kopid): Extension<KOpId>,
+    DomainInfo(domain_info): DomainInfo,
+    Query(link): Query<LoginEmailLinkForm>,
+) -> Response {
+    let display_ctx = LoginDisplayCtx {
+        domain_info,
+        locale: kopid.locale,
+        oauth2: None,
+        reauth: None,
+        error: None,
+    };
+
+    LoginEmailLinkView {
+        display_ctx,
+        token: Some(link.token),
+    }
+    .into_response()
+}
+
+pub async fn view_login_email_link_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+    Form(login_link_form): Form<LoginEmailLinkForm>,
+) -> Response {
+    let auth_cred = AuthCredential::EmailLink(login_link_form.token.trim().to_string());
+    credential_step(state, kopid, jar, client_auth_info, auth_cred, domain_info).await
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoginFederatedCallback {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+pub async fn view_login_federated_get(
+    Extension(kopid): Extension<KOpId>,
+    DomainInfo(domain_info): DomainInfo,
+    Query(callback): Query<LoginFederatedCallback>,
+) -> Response {
+    let (Some(code), Some(state)) = (callback.code, callback.state) else {
+        error!(error = ?callback.error, "Upstream identity provider did not return a code");
+        return UnrecoverableErrorView {
+            err_code: OperationError::AU0008FederatedRequestInvalid,
+            operation_id: kopid.eventid,
+            locale: kopid.locale,
+            domain_info,
+        }
+        .into_response();
+    };
+
+    let display_ctx = LoginDisplayCtx {
+        domain_info,
+        locale: kopid.locale,
+        oauth2: None,
+        reauth: None,
+        error: None,
+    };
+
+    LoginFederatedView {
+        display_ctx,
+        providers: Vec::with_capacity(0),
+        callback: Some((code, state)),
+    }
+    .into_response()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoginFederatedForm {
+    code: String,
+    state: String,
+}
+
+pub async fn view_login_federated_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+    Form(login_federated_form): Form<LoginFederatedForm>,
+) -> Response {
+    let session_context =
+        cookies::get_signed::<SessionContext>(&state, &jar, COOKIE_AUTH_SESSION_ID)
+            .unwrap_or_default();
+
+    let Some(sessionid) = session_context.id else {
+        error!("No auth session in the session cookie");
+        return UnrecoverableErrorView {
+            err_code: OperationError::InvalidSessionState,
+            operation_id: kopid.eventid,
+            locale: kopid.locale,
+            domain_info,
+        }
+        .into_response();
+    };
+
+    let display_ctx = LoginDisplayCtx {
+        domain_info: domain_info.clone(),
+        locale: kopid.locale,
+        oauth2: None,
+        reauth: None,
+        error: None,
+    };
+
+    let inter = state
+        .qe_r_ref
+        .handle_auth_federated(
+            sessionid,
+            login_federated_form.state,
+            login_federated_form.code,
+            kopid.eventid,
+            client_auth_info.clone(),
+        )
+        .await;
+
+    let res = match inter {
+        Ok(ar) => {
+            view_login_step(
+                state,
+                kopid.clone(),
+                jar,
+                ar,
+                client_auth_info,
+                session_context,
+                display_ctx,
+            )
+            .await
+        }
+        Err(err_code) => Err(err_code),
+    };
+
+    res.unwrap_or_else(|err_code| {
+        UnrecoverableErrorView {
+            err_code,
+            operation_id: kopid.eventid,
+            locale: kopid.locale,
+            domain_info,
+        }
+        .into_response()
+    })
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct JsonedPublicKeyCredential {
     cred: String,
@@ -679,6 +1293,85 @@ pub async fn view_login_passkey_post(
     }
 }
 
+/// Complete a login with a passkey that the user selected from the username autofill.
+pub async fn view_login_passkey_autofill_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+    Form(assertion): Form<JsonedPublicKeyCredential>,
+) -> Response {
+    let session_context =
+        cookies::get_signed::<SessionContext>(&state, &jar, COOKIE_AUTH_SESSION_ID)
+            .unwrap_or_default();
+
+    let Some(challenge_id) = session_context.id else {
+        error!("No passkey autofill challenge in the session cookie");
+        return UnrecoverableErrorView {
+            err_code: OperationError::InvalidSessionState,
+            operation_id: kopid.eventid,
+            locale: kopid.locale,
+            domain_info,
+        }
+        .into_response();
+    };
+
+    let pkc = match serde_json::from_str::<Box<PublicKeyCredential>>(assertion.cred.as_str()) {
+        Ok(pkc) => pkc,
+        Err(e) => {
+            error!(err = ?e, "Unable to deserialize credential submission");
+            return HtmxError::new(&kopid, OperationError::SerdeJsonError, domain_info)
+                .into_response();
+        }
+    };
+
+    let display_ctx = LoginDisplayCtx {
+        domain_info: domain_info.clone(),
+        locale: kopid.locale,
+        oauth2: None,
+        reauth: None,
+        error: None,
+    };
+
+    let inter = state
+        .qe_r_ref
+        .handle_auth_discoverable(
+            challenge_id,
+            pkc,
+            AuthIssueSession::Cookie,
+            kopid.eventid,
+            client_auth_info.clone(),
+        )
+        .await;
+
+    let res = match inter {
+        Ok(ar) => {
+            view_login_step(
+                state,
+                kopid.clone(),
+                jar,
+                ar,
+                client_auth_info,
+                session_context,
+                display_ctx,
+            )
+            .await
+        }
+        Err(err_code) => Err(err_code),
+    };
+
+    res.unwrap_or_else(|err_code| {
+        UnrecoverableErrorView {
+            err_code,
+            operation_id: kopid.eventid,
+            locale: kopid.locale,
+            domain_info,
+        }
+        .into_response()
+    })
+}
+
 pub async fn view_login_seckey_post(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
@@ -705,6 +1398,7 @@ async fn credential_step(
 
     let display_ctx = LoginDisplayCtx {
         domain_info: domain_info.clone(),
+        locale: kopid.locale,
         oauth2: None,
         reauth: None,
         error: None,
@@ -741,6 +1435,7 @@ async fn credential_step(
                 Err(err_code) => UnrecoverableErrorView {
                     err_code,
                     operation_id: kopid.eventid,
+                    locale: kopid.locale,
                     domain_info: display_ctx.domain_info,
                 }
                 .into_response(),
@@ -750,6 +1445,7 @@ async fn credential_step(
         Err(err_code) => UnrecoverableErrorView {
             err_code,
             operation_id: kopid.eventid,
+            locale: kopid.locale,
             domain_info,
         }
         .into_response(),
@@ -792,18 +1488,29 @@ async fn view_login_step(
 
                 jar = add_session_cookie(&state, jar, &session_context)?;
 
-                let res = match allowed.len() {
+                // Jwt assertions are signed by automation, and can't be completed in a browser.
+                allowed.retain(|mech| *mech != AuthMech::JwtAssertion);
+
+                // If the user has a preferred mech that is available, select it for them.
+                let preferred = session_context
+                    .mech
+                    .as_ref()
+                    .filter(|mech| allowed.contains(mech))
+                    .cloned();
+
+                let res = match (allowed.len(), preferred) {
                     // Should never happen.
-                    0 => {
+                    (0, _) => {
                         error!("auth state choose allowed mechs is empty");
                         UnrecoverableErrorView {
                             err_code: OperationError::InvalidState,
                             operation_id: kopid.eventid,
+                            locale: kopid.locale,
                             domain_info: display_ctx.domain_info,
                         }
                         .into_response()
                     }
-                    1 => {
+                    (1, _) => {
                         let mech = allowed[0].clone();
                         // submit the choice and then loop updating our auth_state.
                         let inter = state // This may change in the future ...
@@ -825,8 +1532,27 @@ async fn view_login_step(
                         continue;
                     }
 
+                    // The user has a preferred mech that is available, skip the choice.
+                    (_, Some(mech)) => {
+                        let inter = state // This may change in the future ...
+                            .qe_r_ref
+                            .handle_auth(
+                                Some(sessionid),
+                                AuthRequest {
+                                    step: AuthStep::Begin(mech),
+                                },
+                                kopid.eventid,
+                                client_auth_info.clone(),
+                            )
+                            .await?;
+
+                        auth_state = inter.state;
+
+                        continue;
+                    }
+
                     // Render the list of options.
-                    _ => {
+                    (_, None) => {
                         allowed.sort_unstable();
                         // Put strongest first.
                         allowed.reverse();
@@ -836,7 +1562,7 @@ async fn view_login_step(
                             .enumerate()
                             .map(|(i, m)| Mech {
                                 value: m.to_value(),
-                                name: m,
+                                name: display_ctx.locale.auth_mech(&m),
                                 // Auto focus the first item, it's the strongest
                                 // mechanism and the one we should optimise for.
                                 autofocus: i == 0,
@@ -860,6 +1586,7 @@ async fn view_login_step(
                         UnrecoverableErrorView {
                             err_code: OperationError::InvalidState,
                             operation_id: kopid.eventid,
+                            locale: kopid.locale,
                             domain_info: display_ctx.domain_info,
                         }
                         .into_response()
@@ -871,7 +1598,7 @@ async fn view_login_step(
                             AuthAllowed::Totp => LoginTotpView {
                                 display_ctx,
                                 totp: session_context.totp.clone().unwrap_or_default(),
-                                errors: LoginTotpError::default(),
+                                field_errors: FieldErrors::default(),
                             }
                             .into_response(),
                             AuthAllowed::Password => LoginPasswordView {
@@ -882,6 +1609,17 @@ async fn view_login_step(
                             AuthAllowed::BackupCode => {
                                 LoginBackupCodeView { display_ctx }.into_response()
                             }
+                            AuthAllowed::EmailLink => LoginEmailLinkView {
+                                display_ctx,
+                                token: None,
+                            }
+                            .into_response(),
+                            AuthAllowed::Federated(providers) => LoginFederatedView {
+                                display_ctx,
+                                providers,
+                                callback: None,
+                            }
+                            .into_response(),
                             AuthAllowed::SecurityKey(chal) => {
                                 let chal_json = serde_json::to_string(&chal)
                                     .map_err(|_| OperationError::SerdeJsonError)?;
@@ -889,6 +1627,7 @@ async fn view_login_step(
                                     display_ctx,
                                     passkey: false,
                                     chal: chal_json,
+                                    csp_nonce: kopid.csp_nonce.clone(),
                                 }
                                 .into_response()
                             }
@@ -899,6 +1638,7 @@ async fn view_login_step(
                                     display_ctx,
                                     passkey: true,
                                     chal: chal_json,
+                                    csp_nonce: kopid.csp_nonce.clone(),
                                 }
                                 .into_response()
                             }
@@ -917,6 +1657,9 @@ async fn view_login_step(
             }
             AuthState::Success(token, issue) => {
                 debug!("🧩 -> AuthState::Success");
+                state
+                    .login_challenges
+                    .record_success(&session_context.username);
 
                 match issue {
                     AuthIssueSession::Token => {
@@ -937,26 +1680,54 @@ async fn view_login_step(
                         bearer_cookie.make_permanent();
 
                         jar = if session_context.remember_me {
-                            // Important - can be unsigned as username is just for remember
-                            // me and no other purpose.
-                            let mut username_cookie = cookies::make_unsigned(
-                                &state,
-                                COOKIE_USERNAME,
-                                session_context.username.clone(),
-                            );
-                            username_cookie.make_permanent();
-                            jar.add(username_cookie)
+                            let remember_me = RememberMe {
+                                username: session_context.username.clone(),
+                                mech: session_context.mech.clone(),
+                            };
+                            // Signed so that a third party can't plant a username for us
+                            // to begin authenticating as.
+                            let mut remember_me_cookie =
+                                cookies::make_signed(&state, COOKIE_REMEMBER_ME, &remember_me)
+                                    .ok_or(OperationError::InvalidSessionState)?;
+                            remember_me_cookie.make_permanent();
+                            jar.add(remember_me_cookie)
                         } else {
                             jar
                         };
 
                         jar = jar.add(bearer_cookie);
 
+                        // The browser includes the id of a bound session in its proofs, so it
+                        // must be able to read it.
+                        jar = match state
+                            .qe_r_ref
+                            .handle_bound_session_id(*token, kopid.eventid)
+                            .await
+                        {
+                            Ok(Some(session_id)) => {
+                                let mut session_id_cookie = cookies::make_unsigned(
+                                    &state,
+                                    COOKIE_BOUND_SESSION_ID,
+                                    session_id.to_string(),
+                                );
+                                session_id_cookie.set_http_only(false);
+                                session_id_cookie.make_permanent();
+                                jar.add(session_id_cookie)
+                            }
+                            Ok(None) => cookies::destroy(jar, COOKIE_BOUND_SESSION_ID, &state),
+                            Err(err) => {
+                                error!(?err, "Unable to read the session of the issued token");
+                                return Err(err);
+                            }
+                        };
+
                         jar = cookies::destroy(jar, COOKIE_AUTH_SESSION_ID, &state);
 
                         // Now, we need to decided where to go.
                         let res = if jar.get(COOKIE_OAUTH2_REQ).is_some() {
                             Redirect::to(Urls::Oauth2Resume.as_ref()).into_response()
+                        } else if jar.get(COOKIE_SAML2_REQ).is_some() {
+                            Redirect::to(Urls::Saml2Resume.as_ref()).into_response()
                         } else if let Some(auth_loc) = session_context.after_auth_loc {
                             Redirect::to(auth_loc.as_str()).into_response()
                         } else {
@@ -967,13 +1738,38 @@ async fn view_login_step(
                     }
                 }
             }
+            AuthState::CredentialUpdateRequired(token) => {
+                debug!("🧩 -> AuthState::CredentialUpdateRequired");
+                jar = cookies::destroy(jar, COOKIE_AUTH_SESSION_ID, &state);
+
+                // The password has expired, so no session is issued. Instead the user must
+                // change it in a credential update session, and then log in again.
+                let (cu_session_token, _cu_status) = state
+                    .qe_w_ref
+                    .handle_password_expired_exchange(
+                        client_auth_info.clone(),
+                        token.to_string(),
+                        kopid.eventid,
+                    )
+                    .await?;
+
+                jar = add_cu_cookie(jar, &state, cu_session_token);
+
+                break Redirect::to(Urls::CredReset.as_ref()).into_response();
+            }
             AuthState::Denied(reason) => {
                 debug!("🧩 -> AuthState::Denied");
                 jar = cookies::destroy(jar, COOKIE_AUTH_SESSION_ID, &state);
+                state
+                    .login_challenges
+                    .record_failure(&session_context.username);
+
+                let kind = AuthDeniedKind::from_reason(&reason);
+                info!(?kind, %reason, operation_id = %kopid.eventid, "Login denied");
 
                 break LoginDeniedView {
                     display_ctx,
-                    reason,
+                    message_key: denied_message_key(kind),
                     operation_id: kopid.eventid,
                 }
                 .into_response();
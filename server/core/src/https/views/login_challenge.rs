@@ -0,0 +1,298 @@
+//! Proof of work challenges on the login form. Once an account has failed to login a number of
+//! times, new logins to that account must first solve a challenge in the browser. This costs a
+//! person a moment of waiting, but makes guessing passwords from the login form expensive.
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use openssl::sha::sha256;
+use uuid::Uuid;
+
+use crate::config::LoginChallengeConfig;
+
+/// How long after the last failure an account's failures are forgotten.
+const LOGIN_CHALLENGE_FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// How long a challenge may take to be solved.
+const LOGIN_CHALLENGE_VALIDITY: Duration = Duration::from_secs(5 * 60);
+
+/// The most failures and challenges that are tracked before expired ones are removed.
+const LOGIN_CHALLENGE_PRUNE_THRESHOLD: usize = 16384;
+
+/// The highest difficulty that may be configured, since browsers must be able to solve it.
+const LOGIN_CHALLENGE_MAX_DIFFICULTY: u8 = 24;
+
+struct Failures {
+    count: u32,
+    last: Instant,
+}
+
+struct Issued {
+    account: String,
+    expiry: Instant,
+}
+
+/// A challenge to be rendered on the login form.
+pub(crate) struct LoginChallenge {
+    pub(crate) nonce: String,
+    pub(crate) difficulty: u8,
+}
+
+/// The browser's answer to a challenge.
+pub(crate) struct LoginChallengeSolution {
+    pub(crate) nonce: String,
+    pub(crate) solution: String,
+}
+
+pub(crate) struct LoginChallenges {
+    settings: Option<(u32, u8)>,
+    failures: Mutex<BTreeMap<String, Failures>>,
+    issued: Mutex<BTreeMap<String, Issued>>,
+}
+
+fn normalise_account(account: &str) -> String {
+    account.trim().to_lowercase()
+}
+
+fn leading_zero_bits(digest: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in digest {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+fn solution_is_valid(nonce: &str, solution: &str, difficulty: u8) -> bool {
+    let digest = sha256(format!("{}:{}", nonce, solution).as_bytes());
+    leading_zero_bits(&digest) >= u32::from(difficulty)
+}
+
+impl LoginChallenges {
+    pub(crate) fn new(config: Option<&LoginChallengeConfig>) -> Self {
+        LoginChallenges {
+            settings: config.map(|config| {
+                (
+                    config.failure_threshold,
+                    config.difficulty.min(LOGIN_CHALLENGE_MAX_DIFFICULTY),
+                )
+            }),
+            failures: Mutex::new(BTreeMap::new()),
+            issued: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub(crate) fn record_failure(&self, account: &str) {
+        if self.settings.is_none() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if failures.len() >= LOGIN_CHALLENGE_PRUNE_THRESHOLD {
+            failures.retain(|_, f| {
+                now.saturating_duration_since(f.last) < LOGIN_CHALLENGE_FAILURE_WINDOW
+            });
+        }
+
+        let entry = failures
+            .entry(normalise_account(account))
+            .or_insert(Failures {
+                count: 0,
+                last: now,
+            });
+
+        if now.saturating_duration_since(entry.last) >= LOGIN_CHALLENGE_FAILURE_WINDOW {
+            entry.count = 0;
+        }
+        entry.count = entry.count.saturating_add(1);
+        entry.last = now;
+    }
+
+    pub(crate) fn record_success(&self, account: &str) {
+        if self.settings.is_none() {
+            return;
+        }
+
+        self.failures
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&normalise_account(account));
+    }
+
+    fn issue(&self, account: String, difficulty: u8, now: Instant) -> LoginChallenge {
+        let nonce = Uuid::new_v4().simple().to_string();
+
+        let mut issued = self.issued.lock().unwrap_or_else(PoisonError::into_inner);
+        if issued.len() >= LOGIN_CHALLENGE_PRUNE_THRESHOLD {
+            issued.retain(|_, i| i.expiry > now);
+        }
+        issued.insert(
+            nonce.clone(),
+            Issued {
+                account,
+                expiry: now + LOGIN_CHALLENGE_VALIDITY,
+            },
+        );
+
+        LoginChallenge { nonce, difficulty }
+    }
+
+    /// Check whether a login to this account may begin. If a challenge is required and the
+    /// solution is missing or wrong, returns a new challenge to be solved.
+    pub(crate) fn check(
+        &self,
+        account: &str,
+        solution: Option<&LoginChallengeSolution>,
+    ) -> Result<(), LoginChallenge> {
+        self.check_at(account, solution, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        account: &str,
+        solution: Option<&LoginChallengeSolution>,
+        now: Instant,
+    ) -> Result<(), LoginChallenge> {
+        let Some((failure_threshold, difficulty)) = self.settings else {
+            return Ok(());
+        };
+
+        let account = normalise_account(account);
+
+        let required = self
+            .failures
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&account)
+            .is_some_and(|f| {
+                f.count >= failure_threshold
+                    && now.saturating_duration_since(f.last) < LOGIN_CHALLENGE_FAILURE_WINDOW
+            });
+
+        if !required {
+            return Ok(());
+        }
+
+        if let Some(solution) = solution {
+            // A challenge can only be used once, even if the solution is wrong.
+            let issued = self
+                .issued
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&solution.nonce);
+
+            if let Some(issued) = issued {
+                if issued.account == account
+                    && issued.expiry > now
+                    && solution_is_valid(&solution.nonce, &solution.solution, difficulty)
+                {
+                    return Ok(());
+                }
+            }
+            warn!(%account, "Login challenge was not solved");
+        }
+
+        Err(self.issue(account, difficulty, now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{leading_zero_bits, solution_is_valid, LoginChallengeSolution, LoginChallenges};
+    use crate::config::LoginChallengeConfig;
+    use std::time::{Duration, Instant};
+
+    fn solve(nonce: &str, difficulty: u8, valid: bool) -> String {
+        (0u64..)
+            .map(|counter| counter.to_string())
+            .find(|solution| solution_is_valid(nonce, solution, difficulty) == valid)
+            .expect("Unable to solve challenge")
+    }
+
+    #[test]
+    fn test_login_challenge_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x10]), 11);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[test]
+    fn test_login_challenge_disabled() {
+        let challenges = LoginChallenges::new(None);
+        for _ in 0..10 {
+            challenges.record_failure("alice");
+        }
+        assert!(challenges.check("alice", None).is_ok());
+    }
+
+    #[test]
+    fn test_login_challenge_required_after_failures() {
+        let config = LoginChallengeConfig {
+            failure_threshold: 2,
+            difficulty: 8,
+        };
+        let challenges = LoginChallenges::new(Some(&config));
+        let now = Instant::now();
+
+        challenges.record_failure("alice");
+        assert!(challenges.check_at("alice", None, now).is_ok());
+        challenges.record_failure("Alice");
+
+        let challenge = challenges
+            .check_at("alice", None, now)
+            .expect_err("Challenge should be required");
+        assert_eq!(challenge.difficulty, 8);
+
+        // Other accounts are unaffected.
+        assert!(challenges.check_at("bob", None, now).is_ok());
+
+        // A wrong solution is refused, and the challenge can't be retried.
+        let wrong = LoginChallengeSolution {
+            solution: solve(&challenge.nonce, challenge.difficulty, false),
+            nonce: challenge.nonce,
+        };
+        assert!(challenges.check_at("alice", Some(&wrong), now).is_err());
+        let retry = LoginChallengeSolution {
+            solution: solve(&wrong.nonce, 8, true),
+            nonce: wrong.nonce,
+        };
+        assert!(challenges.check_at("alice", Some(&retry), now).is_err());
+
+        let challenge = challenges
+            .check_at("alice", None, now)
+            .expect_err("Challenge should be required");
+        let solution = LoginChallengeSolution {
+            solution: solve(&challenge.nonce, challenge.difficulty, true),
+            nonce: challenge.nonce,
+        };
+
+        // A challenge for one account can't be used for another.
+        challenges.record_failure("bob");
+        challenges.record_failure("bob");
+        let bob_challenge = challenges
+            .check_at("bob", None, now)
+            .expect_err("Challenge should be required");
+        let bob_solution = LoginChallengeSolution {
+            solution: solve(&bob_challenge.nonce, bob_challenge.difficulty, true),
+            nonce: bob_challenge.nonce,
+        };
+        assert!(challenges
+            .check_at("alice", Some(&bob_solution), now)
+            .is_err());
+
+        assert!(challenges.check_at("alice", Some(&solution), now).is_ok());
+        // But only once.
+        assert!(challenges.check_at("alice", Some(&solution), now).is_err());
+
+        // Once the failures are old, or the account logs in, challenges are no longer needed.
+        let later = now + Duration::from_secs(16 * 60);
+        assert!(challenges.check_at("bob", None, later).is_ok());
+        challenges.record_success("alice");
+        assert!(challenges.check_at("alice", None, now).is_ok());
+    }
+}
@@ -7,9 +7,12 @@ use axum::{
 };
 
 use axum_htmx::HxRequestGuardLayer;
+use qrcode::render::svg;
+use qrcode::QrCode;
 
 use crate::https::views::admin::admin_router;
 use constants::Urls;
+use i18n::Locale;
 use kanidmd_lib::{
     idm::server::DomainInfoRead,
     prelude::{OperationError, Uuid},
@@ -18,22 +21,32 @@ use kanidmd_lib::{
 use crate::https::ServerState;
 
 mod admin;
+mod apitokens;
+mod app_passwords;
 mod apps;
+mod consents;
 pub(crate) mod constants;
 mod cookies;
 mod enrol;
 mod errors;
+mod group_requests;
+pub(crate) mod i18n;
 mod login;
+pub(crate) mod login_challenge;
 mod navbar;
 mod oauth2;
 mod profile;
+mod recover;
 mod reset;
+mod saml2;
+mod sessions;
 
 #[derive(Template)]
 #[template(path = "unrecoverable_error.html")]
 struct UnrecoverableErrorView {
     err_code: OperationError,
     operation_id: Uuid,
+    locale: Locale,
     // This is an option because it's not always present in an "unrecoverable" situation
     domain_info: DomainInfoRead,
 }
@@ -43,6 +56,7 @@ struct UnrecoverableErrorView {
 struct ErrorToastPartial {
     err_code: OperationError,
     operation_id: Uuid,
+    locale: Locale,
 }
 
 pub fn view_router() -> Router<ServerState> {
@@ -52,11 +66,33 @@ pub fn view_router() -> Router<ServerState> {
             get(|| async { Redirect::permanent(Urls::Login.as_ref()) }),
         )
         .route("/apps", get(apps::view_apps_get))
+        .route("/api_tokens", get(apitokens::view_api_tokens_get))
+        .route("/app_passwords", get(app_passwords::view_app_passwords_get))
+        .route("/consents", get(consents::view_consents_get))
         .route("/enrol", get(enrol::view_enrol_get))
         .route("/reset", get(reset::view_reset_get))
+        .route(
+            "/recover",
+            get(recover::view_recover_get).post(recover::view_recover_post),
+        )
+        .route("/recover/verify", post(recover::view_recover_verify_post))
         .route("/update_credentials", get(reset::view_self_reset_get))
         .route("/profile", get(profile::view_profile_get))
         .route("/profile/unlock", get(profile::view_profile_unlock_get))
+        .route("/profile/sessions", get(sessions::view_sessions_get))
+        .route(
+            "/profile/group_requests",
+            get(group_requests::view_group_requests_get),
+        )
+        .route("/api/theme", get(profile::view_theme_get))
+        .route(
+            "/api/impersonation_banner",
+            get(navbar::view_impersonation_banner_get),
+        )
+        .route(
+            "/api/session_idle_warning",
+            get(navbar::view_session_idle_warning_get),
+        )
         .route("/logout", get(login::view_logout_get))
         .route("/oauth2", get(oauth2::view_index_get));
 
@@ -70,14 +106,24 @@ pub fn view_router() -> Router<ServerState> {
     unguarded_router = unguarded_router
         .route("/oauth2/resume", get(oauth2::view_resume_get))
         .route("/oauth2/consent", post(oauth2::view_consent_post))
+        .route(
+            "/saml2/:sp_name/sso",
+            get(saml2::view_sso_get).post(saml2::view_sso_post),
+        )
+        .route("/saml2/resume", get(saml2::view_resume_get))
         // The login routes are htmx-free to make them simpler, which means
         // they need manual guarding for direct get requests which can occur
         // if a user attempts to reload the page.
         .route("/login", get(login::view_index_get))
+        .route("/login/forget", get(login::view_login_forget_get))
         .route(
             "/login/passkey",
             post(login::view_login_passkey_post).get(|| async { Redirect::to("/ui") }),
         )
+        .route(
+            "/login/passkey_autofill",
+            post(login::view_login_passkey_autofill_post).get(|| async { Redirect::to("/ui") }),
+        )
         .route(
             "/login/seckey",
             post(login::view_login_seckey_post).get(|| async { Redirect::to("/ui") }),
@@ -101,6 +147,18 @@ pub fn view_router() -> Router<ServerState> {
         .route(
             "/login/pw",
             post(login::view_login_pw_post).get(|| async { Redirect::to("/ui") }),
+        )
+        .route(
+            "/login/email_link",
+            post(login::view_login_email_link_post).get(login::view_login_email_link_get),
+        )
+        .route(
+            "/login/federated/callback",
+            post(login::view_login_federated_post).get(login::view_login_federated_get),
+        )
+        .route(
+            "/login/step_up",
+            post(login::view_login_step_up_post).get(|| async { Redirect::to("/ui") }),
         );
 
     // The webauthn post is unguarded because it's not a htmx event.
@@ -110,6 +168,10 @@ pub fn view_router() -> Router<ServerState> {
         .route("/reset/add_totp", post(reset::view_new_totp))
         .route("/reset/add_password", post(reset::view_new_pwd))
         .route("/reset/change_password", post(reset::view_new_pwd))
+        .route(
+            "/reset/suggest_passphrase",
+            post(reset::view_suggest_passphrase),
+        )
         .route("/reset/add_passkey", post(reset::view_new_passkey))
         .route("/reset/set_unixcred", post(reset::view_set_unixcred))
         .route(
@@ -129,6 +191,46 @@ pub fn view_router() -> Router<ServerState> {
         )
         .route("/api/cu_cancel", post(reset::cancel_cred_update))
         .route("/api/cu_commit", post(reset::commit))
+        .route(
+            "/api/user_settings/theme",
+            post(profile::view_profile_theme_post),
+        )
+        .route(
+            "/api/user_settings/api_token_create",
+            post(apitokens::view_api_token_create_post),
+        )
+        .route(
+            "/api/user_settings/api_token_revoke",
+            post(apitokens::view_api_token_revoke_post),
+        )
+        .route(
+            "/api/user_settings/app_password_create",
+            post(app_passwords::view_app_password_create_post),
+        )
+        .route(
+            "/api/user_settings/app_password_revoke",
+            post(app_passwords::view_app_password_revoke_post),
+        )
+        .route(
+            "/api/user_settings/consent_revoke",
+            post(consents::view_consent_revoke_post),
+        )
+        .route(
+            "/api/user_settings/session_revoke",
+            post(sessions::view_session_revoke_post),
+        )
+        .route(
+            "/api/user_settings/group_request_create",
+            post(group_requests::view_group_request_create_post),
+        )
+        .route(
+            "/api/user_settings/group_request_approve",
+            post(group_requests::view_group_request_approve_post),
+        )
+        .route(
+            "/api/user_settings/group_request_deny",
+            post(group_requests::view_group_request_deny_post),
+        )
         .layer(HxRequestGuardLayer::new("/ui"));
 
     let admin_router = admin_router();
@@ -138,6 +240,24 @@ pub fn view_router() -> Router<ServerState> {
         .nest("/admin", admin_router)
 }
 
+/// Render data as an svg QR code, sized so that it can be scanned from a screen.
+pub(crate) fn render_qr_code_svg(data: &str) -> Option<String> {
+    match QrCode::new(data) {
+        Ok(qr) => Some(
+            qr.render::<svg::Color>()
+                .min_dimensions(200, 200)
+                .dark_color(svg::Color("#000000"))
+                .light_color(svg::Color("#ffffff"))
+                .quiet_zone(true)
+                .build(),
+        ),
+        Err(qr_err) => {
+            error!("Failed to create QR code: {qr_err}");
+            None
+        }
+    }
+}
+
 /// Serde deserialization decorator to map empty Strings to None,
 fn empty_string_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
 where
@@ -169,6 +289,7 @@ mod tests {
         let view = UnrecoverableErrorView {
             err_code: OperationError::InvalidState,
             operation_id: Uuid::new_v4(),
+            locale: Locale::default(),
             domain_info: domain_info.read(),
         };
 
@@ -181,4 +302,14 @@ mod tests {
         // TODO: this really should be an error code :(
         assert_eq!(response.status(), 200);
     }
+
+    #[test]
+    fn test_render_qr_code_svg() {
+        let svg = render_qr_code_svg(
+            "otpauth://totp/example.com:demo?secret=JBSWY3DPEHPK3PXP&issuer=example.com",
+        )
+        .expect("Failed to render QR code");
+        assert!(svg.contains("<svg"));
+        assert!(svg.contains("#000000"));
+    }
 }
@@ -1,5 +1,88 @@
-use crate::https::extractors::DomainInfoRead;
+use askama::Template;
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use kanidmd_lib::idm::account::Account;
+
+use crate::https::extractors::{DomainInfoRead, VerifiedClientInformation};
+use crate::https::middleware::KOpId;
+use crate::https::ServerState;
 
 pub struct NavbarCtx {
     pub domain_info: DomainInfoRead,
 }
+
+#[derive(Template)]
+#[template(path = "navbar_impersonation_banner.html")]
+struct ImpersonationBannerPartial {
+    spn: String,
+    expiry: String,
+}
+
+#[derive(Template)]
+#[template(path = "navbar_session_idle_warning.html")]
+struct SessionIdleWarningPartial {
+    remaining: u64,
+}
+
+/// Loaded by the navbar so that an impersonated session is always visible, without every view
+/// needing to look up the session of the viewer.
+pub(crate) async fn view_impersonation_banner_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Response {
+    let uat = state
+        .qe_r_ref
+        .handle_whoami_uat(client_auth_info, kopid.eventid)
+        .await
+        .ok();
+
+    match uat {
+        Some(uat) if uat.impersonated_by.is_some() => ImpersonationBannerPartial {
+            spn: uat.spn,
+            expiry: uat
+                .expiry
+                .map(|expiry| expiry.to_string())
+                .unwrap_or_default(),
+        }
+        .into_response(),
+        _ => "".into_response(),
+    }
+}
+
+/// Loaded by the navbar when the session has an idle timeout, so that the person is warned before
+/// the session ends. Loading this is a use of the session, so it also keeps the session alive.
+pub(crate) async fn view_session_idle_warning_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Response {
+    // Only validating the session as an identity records its use.
+    if state
+        .qe_r_ref
+        .handle_whoami(client_auth_info.clone(), kopid.eventid)
+        .await
+        .is_err()
+    {
+        return "".into_response();
+    }
+
+    let idle_timeout = state
+        .qe_r_ref
+        .handle_whoami_uat(client_auth_info, kopid.eventid)
+        .await
+        .ok()
+        .and_then(|uat| uat.idle_timeout);
+
+    match idle_timeout {
+        Some(idle_timeout) => SessionIdleWarningPartial {
+            // Use is only recorded once per granularity, so the session may have been last
+            // recorded as used that long ago.
+            remaining: u64::from(idle_timeout)
+                .saturating_sub(Account::session_use_granularity(Some(idle_timeout))),
+        }
+        .into_response(),
+        None => "".into_response(),
+    }
+}
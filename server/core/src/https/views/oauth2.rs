@@ -6,7 +6,10 @@ use crate::https::{
 use kanidmd_lib::idm::oauth2::{AuthorisationRequest, AuthoriseResponse, Oauth2Error};
 use kanidmd_lib::prelude::*;
 
-use kanidm_proto::internal::COOKIE_OAUTH2_REQ;
+use kanidm_proto::constants::{
+    OAUTH2_SCOPE_EMAIL, OAUTH2_SCOPE_GROUPS, OAUTH2_SCOPE_OPENID, OAUTH2_SCOPE_SSH_PUBLICKEYS,
+};
+use kanidm_proto::internal::{Oauth2ScopeDescription, COOKIE_OAUTH2_REQ};
 
 use std::collections::BTreeSet;
 
@@ -16,7 +19,8 @@ use askama::Template;
 use axum::http::StatusCode;
 use axum::{
     extract::{Query, State},
-    http::header::ACCESS_CONTROL_ALLOW_ORIGIN,
+    http::header::{ACCEPT_LANGUAGE, ACCESS_CONTROL_ALLOW_ORIGIN},
+    http::HeaderMap,
     response::{IntoResponse, Redirect, Response},
     Extension, Form,
 };
@@ -31,24 +35,38 @@ use super::{cookies, UnrecoverableErrorView};
 #[template(path = "oauth2_consent_request.html")]
 struct ConsentRequestView {
     client_name: String,
-    // scopes: BTreeSet<String>,
+    scopes: Vec<ScopeDisplay>,
     pii_scopes: BTreeSet<String>,
+    claims: BTreeSet<String>,
     consent_token: String,
     redirect: Option<String>,
 }
 
+struct ScopeDisplay {
+    scope: String,
+    title: String,
+    description: Option<String>,
+}
+
 #[derive(Template)]
 #[template(path = "oauth2_access_denied.html")]
 struct AccessDeniedView {
     operation_id: Uuid,
 }
 
+#[derive(Template)]
+#[template(path = "oauth2_not_assigned.html")]
+struct NotAssignedView {
+    operation_id: Uuid,
+}
+
 pub async fn view_index_get(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     DomainInfo(domain_info): DomainInfo,
     jar: CookieJar,
+    headers: HeaderMap,
     Query(auth_req): Query<AuthorisationRequest>,
 ) -> Response {
     oauth2_auth_req(
@@ -57,6 +75,7 @@ pub async fn view_index_get(
         client_auth_info,
         domain_info,
         jar,
+        &headers,
         Some(auth_req),
     )
     .await
@@ -68,6 +87,7 @@ pub async fn view_resume_get(
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     DomainInfo(domain_info): DomainInfo,
     jar: CookieJar,
+    headers: HeaderMap,
 ) -> Response {
     let maybe_auth_req =
         cookies::get_signed::<AuthorisationRequest>(&state, &jar, COOKIE_OAUTH2_REQ);
@@ -78,6 +98,7 @@ pub async fn view_resume_get(
         client_auth_info,
         domain_info,
         jar,
+        &headers,
         maybe_auth_req,
     )
     .await
@@ -89,6 +110,7 @@ async fn oauth2_auth_req(
     client_auth_info: ClientAuthInfo,
     domain_info: DomainInfoRead,
     jar: CookieJar,
+    headers: &HeaderMap,
     maybe_auth_req: Option<AuthorisationRequest>,
 ) -> Response {
     // No matter what, we always clear the stored oauth2 cookie to prevent
@@ -104,6 +126,7 @@ async fn oauth2_auth_req(
             UnrecoverableErrorView {
                 err_code: OperationError::UI0003InvalidOauth2Resume,
                 operation_id: kopid.eventid,
+                locale: kopid.locale,
                 domain_info,
             },
         )
@@ -132,19 +155,45 @@ async fn oauth2_auth_req(
             )
                 .into_response()
         }
+        Ok(AuthoriseResponse::Rejected(reject)) => {
+            // The client asked that the user not be prompted, so we tell it why we can't proceed.
+            let redirect_uri = reject.build_redirect_uri();
+
+            (
+                jar,
+                [
+                    (HX_REDIRECT, redirect_uri.as_str().to_string()),
+                    (
+                        ACCESS_CONTROL_ALLOW_ORIGIN.as_str(),
+                        redirect_uri.origin().ascii_serialization(),
+                    ),
+                ],
+                Redirect::to(redirect_uri.as_str()),
+            )
+                .into_response()
+        }
         Ok(AuthoriseResponse::ConsentRequested {
             client_name,
-            scopes: _,
+            scopes,
             pii_scopes,
+            claims,
+            scope_descriptions,
             consent_token,
         }) => {
+            let languages = accepted_languages(headers);
+            let scopes = scopes
+                .into_iter()
+                .map(|scope| scope_display(scope, &scope_descriptions, &languages))
+                .collect();
+
             // We can just render the form now, the consent token has everything we need.
             (
                 jar,
                 ConsentRequestView {
                     client_name,
-                    // scopes,
+                    scopes,
                     pii_scopes,
+                    claims,
                     consent_token,
                     redirect: None,
                 },
@@ -158,7 +207,8 @@ async fn oauth2_auth_req(
         }) => {
             // Sign the auth req and hide it in our cookie - we'll come back for
             // you later.
-            let maybe_jar = cookies::make_signed(&state, COOKIE_OAUTH2_REQ, &auth_req)
+            let resume_req = auth_req_for_resume(&auth_req);
+            let maybe_jar = cookies::make_signed(&state, COOKIE_OAUTH2_REQ, &resume_req)
                 .map(|mut cookie| {
                     cookie.set_same_site(SameSite::Strict);
                     // Expire at the end of the session.
@@ -173,18 +223,20 @@ async fn oauth2_auth_req(
                 Ok(new_jar) => {
                     let display_ctx = LoginDisplayCtx {
                         domain_info,
+                        locale: kopid.locale,
                         oauth2: Some(Oauth2Ctx { client_name }),
                         reauth: None,
                         error: None,
                     };
 
-                    super::login::view_oauth2_get(new_jar, display_ctx, login_hint)
+                    super::login::view_oauth2_get(&state, new_jar, display_ctx, login_hint)
                 }
                 Err(err_code) => (
                     jar,
                     UnrecoverableErrorView {
                         err_code,
                         operation_id: kopid.eventid,
+                        locale: kopid.locale,
                         domain_info,
                     },
                 )
@@ -201,6 +253,16 @@ async fn oauth2_auth_req(
             )
                 .into_response()
         }
+        Err(Oauth2Error::ClientNotAssigned) => {
+            // The account is not assigned to this client by an administrator.
+            (
+                jar,
+                NotAssignedView {
+                    operation_id: kopid.eventid,
+                },
+            )
+                .into_response()
+        }
         /*
         RFC - If the request fails due to a missing, invalid, or mismatching
               redirection URI, or if the client identifier is missing or invalid,
@@ -211,6 +273,24 @@ async fn oauth2_auth_req(
         // To further this, it appears that a malicious client configuration can set a phishing
         // site as the redirect URL, and then use that to trigger certain types of attacks. Instead
         // we do NOT redirect in an error condition, and just render the error ourselves.
+        Err(Oauth2Error::RedirectUriRejected(err_code)) => {
+            // Show why, so that the developer of the client can correct the redirect uri.
+            error!(
+                "Unable to authorise - Error ID: {:?} error: {}",
+                kopid.eventid, err_code
+            );
+
+            (
+                jar,
+                UnrecoverableErrorView {
+                    err_code,
+                    operation_id: kopid.eventid,
+                    locale: kopid.locale,
+                    domain_info,
+                },
+            )
+                .into_response()
+        }
         Err(err_code) => {
             error!(
                 "Unable to authorise - Error ID: {:?} error: {}",
@@ -223,6 +303,7 @@ async fn oauth2_auth_req(
                 UnrecoverableErrorView {
                     err_code: OperationError::InvalidState,
                     operation_id: kopid.eventid,
+                    locale: kopid.locale,
                     domain_info,
                 },
             )
@@ -231,12 +312,110 @@ async fn oauth2_auth_req(
     }
 }
 
+/// The user is about to authenticate, so when they resume the request it must not ask them
+/// to authenticate again.
+fn auth_req_for_resume(auth_req: &AuthorisationRequest) -> AuthorisationRequest {
+    let mut resume_req = auth_req.clone();
+    resume_req.max_age = None;
+    resume_req.oidc_ext.prompt = auth_req
+        .oidc_ext
+        .prompt
+        .as_deref()
+        .map(|prompt| {
+            prompt
+                .split_ascii_whitespace()
+                .filter(|value| *value != "login")
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|prompt| !prompt.is_empty());
+    resume_req
+}
+
+/// The languages the user agent accepts, most preferred first, as lower case language tags.
+fn accepted_languages(headers: &HeaderMap) -> Vec<String> {
+    let mut languages: Vec<(String, f32)> = headers
+        .get_all(ACCEPT_LANGUAGE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = parts.next()?.trim().to_ascii_lowercase();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map(|q| q.parse::<f32>().unwrap_or(0.0))
+                .unwrap_or(1.0);
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+
+    // This is a stable sort, so equally weighted languages remain in the order they were sent.
+    languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+    languages.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Choose how to present a scope to the user. A description in the users preferred language
+/// is used first, then a description without a language, and finally a built in description
+/// for the well known scopes.
+fn scope_display(
+    scope: String,
+    descriptions: &[Oauth2ScopeDescription],
+    languages: &[String],
+) -> ScopeDisplay {
+    let for_scope = || descriptions.iter().filter(|desc| desc.scope == scope);
+
+    let localised = languages.iter().find_map(|language| {
+        let primary = language.split('-').next().unwrap_or(language);
+        for_scope()
+            .find(|desc| desc.language.as_deref() == Some(language.as_str()))
+            .or_else(|| {
+                for_scope().find(|desc| {
+                    desc.language.as_deref().and_then(|l| l.split('-').next()) == Some(primary)
+                })
+            })
+    });
+
+    if let Some(desc) = localised.or_else(|| for_scope().find(|desc| desc.language.is_none())) {
+        return ScopeDisplay {
+            title: desc.title.clone(),
+            description: desc.description.clone(),
+            scope,
+        };
+    }
+
+    let builtin = match scope.as_str() {
+        OAUTH2_SCOPE_OPENID => Some(("Sign in", "Confirm your identity with this site.")),
+        "profile" => Some(("Profile", "Your name, display name and username.")),
+        OAUTH2_SCOPE_EMAIL => Some(("Email", "Your email addresses.")),
+        OAUTH2_SCOPE_GROUPS => Some(("Groups", "The groups that you are a member of.")),
+        OAUTH2_SCOPE_SSH_PUBLICKEYS => Some(("SSH Public Keys", "Your SSH public keys.")),
+        _ => None,
+    };
+
+    match builtin {
+        Some((title, description)) => ScopeDisplay {
+            title: title.to_string(),
+            description: Some(description.to_string()),
+            scope,
+        },
+        None => ScopeDisplay {
+            title: scope.clone(),
+            description: None,
+            scope,
+        },
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ConsentForm {
     consent_token: String,
     #[serde(default)]
     #[allow(dead_code)] // TODO: do smoething with this
     redirect: Option<String>,
+    // Only submitted when the user pressed the deny button.
+    #[serde(default)]
+    deny: Option<String>,
 }
 
 pub async fn view_consent_post(
@@ -247,6 +426,18 @@ pub async fn view_consent_post(
     jar: CookieJar,
     Form(consent_form): Form<ConsentForm>,
 ) -> Result<Response, UnrecoverableErrorView> {
+    if consent_form.deny.is_some() {
+        return view_consent_deny(
+            server_state,
+            kopid,
+            client_auth_info,
+            domain_info,
+            jar,
+            consent_form.consent_token,
+        )
+        .await;
+    }
+
     let res = server_state
         .qe_w_ref
         .handle_oauth2_authorise_permit(client_auth_info, consent_form.consent_token, kopid.eventid)
@@ -295,12 +486,58 @@ pub async fn view_consent_post(
             Err(UnrecoverableErrorView {
                 err_code: OperationError::InvalidState,
                 operation_id: kopid.eventid,
+                locale: kopid.locale,
                 domain_info,
             })
         }
     }
 }
 
+/// The user declined to consent, so the client is told that access was denied.
+async fn view_consent_deny(
+    server_state: ServerState,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+    domain_info: DomainInfoRead,
+    jar: CookieJar,
+    consent_token: String,
+) -> Result<Response, UnrecoverableErrorView> {
+    let reject = server_state
+        .qe_r_ref
+        .handle_oauth2_authorise_reject(client_auth_info, consent_token, kopid.eventid)
+        .await
+        .map_err(|err_code| {
+            error!(
+                "Unable to deny consent - Error ID: {:?} error: {}",
+                kopid.eventid,
+                &err_code.to_string()
+            );
+
+            UnrecoverableErrorView {
+                err_code: OperationError::InvalidState,
+                operation_id: kopid.eventid,
+                locale: kopid.locale,
+                domain_info,
+            }
+        })?;
+
+    let jar = cookies::destroy(jar, COOKIE_OAUTH2_REQ, &server_state);
+    let redirect_uri = reject.build_redirect_uri();
+
+    Ok((
+        jar,
+        [
+            (HX_REDIRECT, redirect_uri.as_str().to_string()),
+            (
+                ACCESS_CONTROL_ALLOW_ORIGIN.as_str(),
+                redirect_uri.origin().ascii_serialization(),
+            ),
+        ],
+        Redirect::to(redirect_uri.as_str()),
+    )
+        .into_response())
+}
+
 #[derive(Template, Debug, Clone)]
 #[cfg(feature = "dev-oauth2-device-flow")]
 #[template(path = "oauth2_device_login.html")]
@@ -354,3 +591,52 @@ pub async fn view_device_post(
     // then start a login flow which ends up authorizing the token at the end.
     Err((StatusCode::NOT_IMPLEMENTED, "Not implemented yet"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{accepted_languages, scope_display};
+    use axum::http::header::ACCEPT_LANGUAGE;
+    use axum::http::{HeaderMap, HeaderValue};
+    use kanidm_proto::internal::Oauth2ScopeDescription;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_oauth2_consent_scope_display() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            ACCEPT_LANGUAGE,
+            HeaderValue::from_static("fr-CH, de;q=0.7, en;q=0.8, *;q=0.5"),
+        );
+        let languages = accepted_languages(&headers);
+        assert_eq!(languages, vec!["fr-ch", "en", "de"]);
+
+        let descriptions: Vec<_> = [
+            "files=Files|Read your files",
+            "files@de=Dateien|Ihre Dateien lesen",
+            "files@fr=Fichiers",
+        ]
+        .into_iter()
+        .map(|d| Oauth2ScopeDescription::from_str(d).expect("invalid description"))
+        .collect();
+
+        // The primary language subtag matches.
+        let display = scope_display("files".to_string(), &descriptions, &languages);
+        assert_eq!(display.title, "Fichiers");
+        assert_eq!(display.description, None);
+
+        let display = scope_display("files".to_string(), &descriptions, &["de-at".to_string()]);
+        assert_eq!(display.title, "Dateien");
+
+        // Falls back to the description without a language.
+        let display = scope_display("files".to_string(), &descriptions, &[]);
+        assert_eq!(display.title, "Files");
+        assert_eq!(display.description.as_deref(), Some("Read your files"));
+
+        // Well known scopes have a default, and anything else is shown as is.
+        let display = scope_display("groups".to_string(), &descriptions, &languages);
+        assert_eq!(display.title, "Groups");
+        let display = scope_display("custom".to_string(), &descriptions, &languages);
+        assert_eq!(display.title, "custom");
+        assert_eq!(display.scope, "custom");
+    }
+}
@@ -4,10 +4,15 @@ use crate::https::middleware::KOpId;
 use crate::https::ServerState;
 use askama::Template;
 use axum::extract::State;
-use axum::response::Response;
-use axum::Extension;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Form, Json};
 use axum_extra::extract::cookie::CookieJar;
-use kanidm_proto::internal::UserAuthToken;
+use axum_htmx::{HxEvent, HxResponseTrigger};
+use kanidm_proto::internal::{OperationError, UiTheme, UserAuthToken};
+use kanidmd_lib::prelude::*;
+use serde::Deserialize;
+use std::str::FromStr;
 
 use super::constants::{ProfileMenuItems, UiMessage, Urls};
 use super::errors::HtmxError;
@@ -29,6 +34,14 @@ struct ProfilePartialView {
     account_name: String,
     display_name: String,
     email: Option<String>,
+    theme: Option<UiTheme>,
+    domain_theme: UiTheme,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ThemeForm {
+    // An empty theme resets the person to the domain default.
+    theme: String,
 }
 
 pub(crate) async fn view_profile_get(
@@ -39,13 +52,19 @@ pub(crate) async fn view_profile_get(
 ) -> Result<ProfileView, WebError> {
     let uat: UserAuthToken = state
         .qe_r_ref
-        .handle_whoami_uat(client_auth_info, kopid.eventid)
+        .handle_whoami_uat(client_auth_info.clone(), kopid.eventid)
         .await?;
 
     let time = time::OffsetDateTime::now_utc() + time::Duration::new(60, 0);
 
     let can_rw = uat.purpose_readwrite_active(time);
 
+    let theme = state
+        .qe_r_ref
+        .handle_ui_theme(client_auth_info, kopid.eventid)
+        .await?;
+    let domain_theme = domain_info.theme();
+
     Ok(ProfileView {
         navbar_ctx: NavbarCtx { domain_info },
 
@@ -55,10 +74,90 @@ pub(crate) async fn view_profile_get(
             account_name: uat.name().to_string(),
             display_name: uat.displayname.clone(),
             email: uat.mail_primary.clone(),
+            theme,
+            domain_theme,
         },
     })
 }
 
+/// The theme of the web interface. This is the theme chosen by the person if they are
+/// authenticated and have made a choice, otherwise it is the default of the domain.
+pub(crate) async fn view_theme_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+) -> Json<UiTheme> {
+    let theme = state
+        .qe_r_ref
+        .handle_ui_theme(client_auth_info, kopid.eventid)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| domain_info.theme());
+
+    Json(theme)
+}
+
+pub(crate) async fn view_profile_theme_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<ThemeForm>,
+) -> Result<Response, HtmxError> {
+    let uat: UserAuthToken = state
+        .qe_r_ref
+        .handle_whoami_uat(client_auth_info.clone(), kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    let filter = filter_all!(f_eq(Attribute::Class, EntryClass::Account.into()));
+
+    let result = match form.theme.as_str() {
+        "" => {
+            state
+                .qe_w_ref
+                .handle_purgeattribute(
+                    client_auth_info,
+                    uat.uuid.to_string(),
+                    Attribute::UiTheme.to_string(),
+                    filter,
+                    kopid.eventid,
+                )
+                .await
+        }
+        theme => {
+            let theme = UiTheme::from_str(theme).map_err(|_| {
+                HtmxError::new(
+                    &kopid,
+                    OperationError::InvalidRequestState,
+                    domain_info.clone(),
+                )
+            })?;
+
+            state
+                .qe_w_ref
+                .handle_setattribute(
+                    client_auth_info,
+                    uat.uuid.to_string(),
+                    Attribute::UiTheme.to_string(),
+                    vec![theme.to_string()],
+                    filter,
+                    kopid.eventid,
+                )
+                .await
+        }
+    };
+
+    result.map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info))?;
+
+    // Let the page know that it needs to fetch and apply the new theme.
+    let theme_trigger =
+        HxResponseTrigger::after_swap([HxEvent::new("kanidmThemeChanged".to_string())]);
+    Ok((theme_trigger, StatusCode::OK).into_response())
+}
+
 pub(crate) async fn view_profile_unlock_get(
     State(state): State<ServerState>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
@@ -74,6 +173,7 @@ pub(crate) async fn view_profile_unlock_get(
 
     let display_ctx = LoginDisplayCtx {
         domain_info,
+        locale: kopid.locale,
         oauth2: None,
         reauth: Some(Reauth {
             username: uat.spn,
@@ -0,0 +1,144 @@
+use askama::Template;
+use axum::extract::State;
+use axum::http::Uri;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Form};
+use axum_extra::extract::CookieJar;
+use axum_htmx::{HxLocation, HxPushUrl};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use kanidm_proto::internal::OperationError;
+
+use super::constants::Urls;
+use super::errors::HtmxError;
+use super::reset::add_cu_cookie;
+use crate::https::extractors::{DomainInfo, DomainInfoRead, VerifiedClientInformation};
+use crate::https::middleware::KOpId;
+use crate::https::ServerState;
+
+#[derive(Template)]
+#[template(path = "account_recovery.html")]
+struct AccountRecoveryView {
+    domain_info: DomainInfoRead,
+    available: bool,
+    // When set, a code has been requested and we are waiting for it to be entered.
+    recovery_id: Option<Uuid>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct RecoverBeginForm {
+    username: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct RecoverVerifyForm {
+    recovery_id: Uuid,
+    code: String,
+}
+
+pub(crate) async fn view_recover_get(
+    State(state): State<ServerState>,
+    DomainInfo(domain_info): DomainInfo,
+) -> Response {
+    (
+        HxPushUrl(Uri::from_static(Urls::AccountRecovery.as_ref())),
+        AccountRecoveryView {
+            domain_info,
+            available: state.qe_w_ref.account_recovery_enabled(),
+            recovery_id: None,
+            error: None,
+        },
+    )
+        .into_response()
+}
+
+pub(crate) async fn view_recover_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<RecoverBeginForm>,
+) -> axum::response::Result<Response> {
+    // This always succeeds when recovery is available, regardless of whether the account exists,
+    // so that this view can't be used to discover account names.
+    match state
+        .qe_w_ref
+        .handle_account_recovery_begin(client_auth_info, form.username, kopid.eventid)
+        .await
+    {
+        Ok(recovery_id) => Ok(AccountRecoveryView {
+            domain_info,
+            available: true,
+            recovery_id: Some(recovery_id),
+            error: None,
+        }
+        .into_response()),
+        Err(OperationError::KG004MailNotConfigured) => Ok(AccountRecoveryView {
+            domain_info,
+            available: false,
+            recovery_id: None,
+            error: None,
+        }
+        .into_response()),
+        Err(op_err) => Err(HtmxError::new(&kopid, op_err, domain_info).into()),
+    }
+}
+
+pub(crate) async fn view_recover_verify_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+    Form(form): Form<RecoverVerifyForm>,
+) -> axum::response::Result<Response> {
+    match state
+        .qe_w_ref
+        .handle_account_recovery_verify(
+            client_auth_info,
+            form.recovery_id,
+            form.code,
+            kopid.eventid,
+        )
+        .await
+    {
+        Ok((cu_session_token, _cu_status)) => {
+            // The recovery is complete, hand over to the credential reset view with the new
+            // session in place.
+            let jar = add_cu_cookie(jar, &state, cu_session_token);
+            Ok((
+                jar,
+                HxLocation::from(Uri::from_static(Urls::CredReset.as_ref())),
+                "",
+            )
+                .into_response())
+        }
+        Err(OperationError::AccessDenied) => Ok(AccountRecoveryView {
+            domain_info,
+            available: true,
+            recovery_id: Some(form.recovery_id),
+            error: Some("The recovery code is incorrect.".to_string()),
+        }
+        .into_response()),
+        Err(OperationError::SessionExpired) => Ok(AccountRecoveryView {
+            domain_info,
+            available: true,
+            recovery_id: None,
+            error: Some(
+                "The recovery code has expired or too many attempts were made. Request a new code."
+                    .to_string(),
+            ),
+        }
+        .into_response()),
+        Err(OperationError::KG004MailNotConfigured) => Ok(AccountRecoveryView {
+            domain_info,
+            available: false,
+            recovery_id: None,
+            error: None,
+        }
+        .into_response()),
+        Err(op_err) => Err(HtmxError::new(&kopid, op_err, domain_info).into()),
+    }
+}
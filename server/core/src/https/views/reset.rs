@@ -10,14 +10,13 @@ use axum_htmx::{
     SwapOption,
 };
 use futures_util::TryFutureExt;
-use qrcode::render::svg;
-use qrcode::QrCode;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
+use time::format_description::well_known::Rfc3339;
 use uuid::Uuid;
 
 pub use sshkey_attest::proto::PublicKey as SshPublicKey;
@@ -36,8 +35,10 @@ use crate::https::extractors::{DomainInfo, DomainInfoRead, VerifiedClientInforma
 use crate::https::middleware::KOpId;
 use crate::https::views::constants::ProfileMenuItems;
 use crate::https::views::cookies;
-use crate::https::views::errors::HtmxError;
+use crate::https::views::errors::{FieldErrors, HtmxError};
+use crate::https::views::i18n::Locale;
 use crate::https::views::login::{LoginDisplayCtx, Reauth, ReauthPurpose};
+use crate::https::views::render_qr_code_svg;
 use crate::https::ServerState;
 
 use super::UnrecoverableErrorView;
@@ -53,6 +54,7 @@ struct ProfileView {
 #[template(path = "credentials_reset_form.html")]
 struct ResetCredFormView {
     domain_info: DomainInfoRead,
+    locale: Locale,
     wrong_code: bool,
 }
 
@@ -95,6 +97,9 @@ struct CredResetPartialView {
     unixcred: Option<CredentialDetail>,
     sshkeys_state: CUCredState,
     sshkeys: BTreeMap<String, SshKey>,
+    // When the reset token used for this session expires, and its remaining uses.
+    intent_expiry_time: Option<String>,
+    intent_uses_remaining: Option<u32>,
 }
 
 #[skip_serializing_none]
@@ -107,30 +112,37 @@ pub(crate) struct ResetTokenParam {
 #[derive(Template)]
 #[template(path = "credential_update_add_password_partial.html")]
 struct AddPasswordPartial {
-    check_res: PwdCheckResult,
+    locale: Locale,
+    field_errors: FieldErrors,
+    suggestion: Option<String>,
 }
 
 #[derive(Template)]
 #[template(path = "credential_update_set_unixcred_partial.html")]
 struct SetUnixCredPartial {
-    check_res: PwdCheckResult,
+    field_errors: FieldErrors,
 }
 
 #[derive(Template)]
 #[template(path = "credential_update_add_ssh_publickey_partial.html")]
 struct AddSshPublicKeyPartial {
-    title_error: Option<String>,
-    key_error: Option<String>,
+    field_errors: FieldErrors,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-enum PwdCheckResult {
-    Success,
-    Init,
-    Failure {
-        pwd_equal: bool,
-        warnings: Vec<PasswordFeedback>,
-    },
+/// The reasons a new password was rejected, shown against the field that caused them.
+fn password_field_errors(
+    locale: Locale,
+    pwd_equal: bool,
+    warnings: Vec<PasswordFeedback>,
+) -> FieldErrors {
+    let mut field_errors: FieldErrors = warnings
+        .into_iter()
+        .map(|warn| ("new-password", warn.to_string()))
+        .collect();
+    if !pwd_equal {
+        field_errors.push("new-password-check", locale.t("password.mismatch"));
+    }
+    field_errors
 }
 
 #[derive(Deserialize, Debug)]
@@ -165,6 +177,8 @@ struct AddPasskeyPartial {
     // Passkey challenge for adding a new passkey
     challenge: String,
     class: PasskeyClass,
+    // Inline scripts must carry the nonce of the content security policy.
+    csp_nonce: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -198,8 +212,9 @@ pub(crate) struct TOTPRemoveData {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct TotpInit {
+    /// The secret split into groups of four characters so it can be entered by hand.
     secret: String,
-    qr_code_svg: String,
+    qr_code_svg: Option<String>,
     steps: u64,
     digits: u8,
     algo: TotpAlgo,
@@ -214,6 +229,32 @@ pub(crate) struct TotpCheck {
     taken_name: Option<String>,
 }
 
+impl TotpCheck {
+    fn field_errors(&self) -> FieldErrors {
+        let mut field_errors = FieldErrors::default();
+        if self.broken_app {
+            field_errors.push("new-totp-check", "Your authenticator appears to be implemented in a way that uses SHA1, rather than SHA256. Are you sure you want to proceed? If you want to try with a new authenticator, enter a new code.");
+        } else if self.wrong_code {
+            field_errors.push("new-totp-check", "Incorrect TOTP code - Please try again");
+        }
+        if self.bad_name {
+            field_errors.push(
+                "new-totp-name",
+                "The name you provided was empty or blank. Please provide a proper name",
+            );
+        } else if let Some(name) = &self.taken_name {
+            field_errors.push(
+                "new-totp-name",
+                format!(
+                    "The name \"{}\" is either invalid or already taken, Please pick a different one",
+                    name
+                ),
+            );
+        }
+        field_errors
+    }
+}
+
 #[derive(Template)]
 #[template(path = "credential_update_add_totp_partial.html")]
 struct AddTotpPartial {
@@ -509,12 +550,14 @@ pub(crate) async fn view_new_passkey(
                 AddPasskeyPartial {
                     challenge,
                     class: init_form.class,
+                    csp_nonce: kopid.csp_nonce.clone(),
                 }
                 .into_response()
             } else {
                 UnrecoverableErrorView {
                     err_code: OperationError::UI0001ChallengeSerialisation,
                     operation_id: kopid.eventid,
+                    locale: kopid.locale,
                     domain_info,
                 }
                 .into_response()
@@ -523,6 +566,7 @@ pub(crate) async fn view_new_passkey(
         _ => UnrecoverableErrorView {
             err_code: OperationError::UI0002InvalidState,
             operation_id: kopid.eventid,
+            locale: kopid.locale,
             domain_info,
         }
         .into_response(),
@@ -557,18 +601,12 @@ pub(crate) async fn view_new_totp(
 
     let partial = if let CURegState::TotpCheck(secret) = cu_status.mfaregstate {
         let uri = secret.to_uri();
-        let svg = match QrCode::new(uri.as_str()) {
-            Ok(qr) => qr.render::<svg::Color>().build(),
-            Err(qr_err) => {
-                error!("Failed to create TOTP QR code: {qr_err}");
-                "QR Code Generation Failed".to_string()
-            }
-        };
+        let qr_code_svg = render_qr_code_svg(uri.as_str());
 
         AddTotpPartial {
             totp_init: Some(TotpInit {
-                secret: secret.get_secret(),
-                qr_code_svg: svg,
+                secret: group_totp_secret(&secret.get_secret()),
+                qr_code_svg,
                 steps: secret.step,
                 digits: secret.digits,
                 algo: secret.algo,
@@ -589,6 +627,17 @@ pub(crate) async fn view_new_totp(
     Ok((push_url, partial).into_response())
 }
 
+/// Split a base32 TOTP secret into space separated groups of four characters, which
+/// authenticators accept when the secret is entered by hand.
+fn group_totp_secret(secret: &str) -> String {
+    secret
+        .as_bytes()
+        .chunks(4)
+        .filter_map(|chunk| std::str::from_utf8(chunk).ok())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub(crate) async fn add_totp(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
@@ -701,7 +750,9 @@ pub(crate) async fn view_new_pwd(
             return Ok((
                 swapped_handler_trigger,
                 AddPasswordPartial {
-                    check_res: PwdCheckResult::Init,
+                    locale: kopid.locale,
+                    field_errors: FieldErrors::default(),
+                    suggestion: None,
                 },
             )
                 .into_response());
@@ -736,16 +787,44 @@ pub(crate) async fn view_new_pwd(
         (vec![], StatusCode::UNPROCESSABLE_ENTITY)
     };
 
-    let check_res = PwdCheckResult::Failure {
-        pwd_equal,
-        warnings,
-    };
-
     Ok((
         status,
         swapped_handler_trigger,
         HxPushUrl(Uri::from_static("/ui/reset/change_password")),
-        AddPasswordPartial { check_res },
+        AddPasswordPartial {
+            locale: kopid.locale,
+            field_errors: password_field_errors(kopid.locale, pwd_equal, warnings),
+            suggestion: None,
+        },
+    )
+        .into_response())
+}
+
+pub(crate) async fn view_suggest_passphrase(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    HxRequest(_hx_request): HxRequest,
+    VerifiedClientInformation(_client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+) -> axum::response::Result<Response> {
+    let cu_session_token: CUSessionToken = get_cu_session(&jar).await?;
+    let swapped_handler_trigger =
+        HxResponseTrigger::after_swap([HxEvent::new("addPasswordSwapped".to_string())]);
+
+    let suggestion = state
+        .qe_r_ref
+        .handle_idmcredentialsuggestpassphrase(cu_session_token, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info))?;
+
+    Ok((
+        swapped_handler_trigger,
+        AddPasswordPartial {
+            locale: kopid.locale,
+            field_errors: FieldErrors::default(),
+            suggestion: Some(suggestion),
+        },
     )
         .into_response())
 }
@@ -754,7 +833,7 @@ pub(crate) async fn view_new_pwd(
 pub(crate) async fn view_self_reset_get(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
-    HxRequest(_hx_request): HxRequest,
+    HxRequest(hx_request): HxRequest,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     DomainInfo(domain_info): DomainInfo,
     mut jar: CookieJar,
@@ -782,6 +861,7 @@ pub(crate) async fn view_self_reset_get(
     } else {
         let display_ctx = LoginDisplayCtx {
             domain_info,
+            locale: kopid.locale,
             oauth2: None,
             reauth: Some(Reauth {
                 username: uat.spn,
@@ -790,11 +870,12 @@ pub(crate) async fn view_self_reset_get(
             error: None,
         };
 
-        Ok(super::login::view_reauth_get(
+        Ok(super::login::view_step_up_get(
             state,
             client_auth_info,
             kopid,
             jar,
+            hx_request,
             Urls::UpdateCredentials.as_ref(),
             display_ctx,
         )
@@ -803,7 +884,7 @@ pub(crate) async fn view_self_reset_get(
 }
 
 // Adds the COOKIE_CU_SESSION_TOKEN to the jar and returns the result
-fn add_cu_cookie(
+pub(super) fn add_cu_cookie(
     jar: CookieJar,
     state: &ServerState,
     cu_session_token: CUSessionToken,
@@ -832,7 +913,7 @@ pub(crate) async fn view_set_unixcred(
             return Ok((
                 swapped_handler_trigger,
                 SetUnixCredPartial {
-                    check_res: PwdCheckResult::Init,
+                    field_errors: FieldErrors::default(),
                 },
             )
                 .into_response());
@@ -867,25 +948,17 @@ pub(crate) async fn view_set_unixcred(
         (vec![], StatusCode::UNPROCESSABLE_ENTITY)
     };
 
-    let check_res = PwdCheckResult::Failure {
-        pwd_equal,
-        warnings,
-    };
-
     Ok((
         status,
         swapped_handler_trigger,
         HxPushUrl(Uri::from_static("/ui/reset/set_unixcred")),
-        AddPasswordPartial { check_res },
+        SetUnixCredPartial {
+            field_errors: password_field_errors(pwd_equal, warnings),
+        },
     )
         .into_response())
 }
 
-struct AddSshPublicKeyError {
-    key: Option<String>,
-    title: Option<String>,
-}
-
 pub(crate) async fn view_add_ssh_publickey(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
@@ -900,72 +973,50 @@ pub(crate) async fn view_add_ssh_publickey(
     let new_key = match opt_form {
         None => {
             return Ok((AddSshPublicKeyPartial {
-                title_error: None,
-                key_error: None,
+                field_errors: FieldErrors::default(),
             },)
                 .into_response());
         }
         Some(Form(new_key)) => new_key,
     };
 
-    let (
-        AddSshPublicKeyError {
-            key: key_error,
-            title: title_error,
-        },
-        status,
-    ) = {
-        let publickey = match SshPublicKey::from_string(&new_key.key) {
-            Err(_) => {
-                return Ok((AddSshPublicKeyPartial {
-                    title_error: None,
-                    key_error: Some("Key cannot be parsed".to_string()),
-                },)
-                    .into_response());
-            }
-            Ok(publickey) => publickey,
-        };
-        let res = state
-            .qe_r_ref
-            .handle_idmcredentialupdate(
-                cu_session_token,
-                CURequest::SshPublicKey(new_key.title, publickey),
-                kopid.eventid,
-            )
-            .await;
-        match res {
-            Ok(cu_status) => return Ok(get_cu_partial_response(cu_status)),
-            Err(e @ (OperationError::InvalidLabel | OperationError::DuplicateLabel)) => (
-                AddSshPublicKeyError {
-                    title: Some(e.to_string()),
-                    key: None,
-                },
-                StatusCode::UNPROCESSABLE_ENTITY,
-            ),
-            Err(e @ OperationError::DuplicateKey) => (
-                AddSshPublicKeyError {
-                    key: Some(e.to_string()),
-                    title: None,
-                },
-                StatusCode::UNPROCESSABLE_ENTITY,
-            ),
-            Err(operr) => {
-                return Err(ErrorResponse::from(HtmxError::new(
-                    &kopid,
-                    operr,
-                    domain_info,
-                )))
+    let mut field_errors = FieldErrors::default();
+
+    match SshPublicKey::from_string(&new_key.key) {
+        Err(_) => field_errors.push("key-content", "Key cannot be parsed"),
+        Ok(publickey) => {
+            let res = state
+                .qe_r_ref
+                .handle_idmcredentialupdate(
+                    cu_session_token,
+                    CURequest::SshPublicKey(new_key.title, publickey),
+                    kopid.eventid,
+                )
+                .await;
+            match res {
+                Ok(cu_status) => return Ok(get_cu_partial_response(cu_status)),
+                Err(e @ (OperationError::InvalidLabel | OperationError::DuplicateLabel)) => {
+                    field_errors.push("key-title", e.to_string())
+                }
+                Err(
+                    e @ (OperationError::DuplicateKey
+                    | OperationError::PL0003SshPublicKeyPolicyDenied),
+                ) => field_errors.push("key-content", e.to_string()),
+                Err(operr) => {
+                    return Err(ErrorResponse::from(HtmxError::new(
+                        &kopid,
+                        operr,
+                        domain_info,
+                    )))
+                }
             }
         }
     };
 
     Ok((
-        status,
+        StatusCode::UNPROCESSABLE_ENTITY,
         HxPushUrl(Uri::from_static("/ui/reset/add_ssh_publickey")),
-        AddSshPublicKeyPartial {
-            title_error,
-            key_error,
-        },
+        AddSshPublicKeyPartial { field_errors },
     )
         .into_response())
 }
@@ -1040,6 +1091,7 @@ pub(crate) async fn view_reset_get(
                     push_url,
                     ResetCredFormView {
                         domain_info,
+                        locale: kopid.locale,
                         wrong_code: true,
                     },
                 )
@@ -1055,6 +1107,7 @@ pub(crate) async fn view_reset_get(
             push_url,
             ResetCredFormView {
                 domain_info,
+                locale: kopid.locale,
                 wrong_code: false,
             },
         )
@@ -1077,9 +1130,13 @@ fn get_cu_partial(cu_status: CUStatus) -> CredResetPartialView {
         unixcred,
         sshkeys_state,
         sshkeys,
+        intent_expiry_time,
+        intent_uses_remaining,
         ..
     } = cu_status;
 
+    let intent_expiry_time = intent_expiry_time.and_then(|odt| odt.format(&Rfc3339).ok());
+
     let sshkeyss: BTreeMap<String, SshKey> = sshkeys
         .iter()
         .map(|(k, v)| {
@@ -1108,6 +1165,8 @@ fn get_cu_partial(cu_status: CUStatus) -> CredResetPartialView {
         unixcred,
         sshkeys_state,
         sshkeys: sshkeyss,
+        intent_expiry_time,
+        intent_uses_remaining,
     }
 }
 
@@ -1179,3 +1238,39 @@ async fn get_cu_session(jar: &CookieJar) -> Result<CUSessionToken, Response> {
             .into_response())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{password_field_errors, AddPasswordPartial, Locale};
+    use askama::Template;
+    use kanidm_proto::internal::PasswordFeedback;
+
+    #[test]
+    fn test_add_password_partial_field_errors() {
+        let view = AddPasswordPartial {
+            locale: Locale::En,
+            field_errors: password_field_errors(
+                Locale::En,
+                false,
+                vec![PasswordFeedback::TooShort(14)],
+            ),
+            suggestion: None,
+        };
+        let html = view.render().expect("Failed to render");
+
+        // Each error is linked from the summary, and described on the field that caused it.
+        assert!(html.contains(r##"<a href="#new-password" class="alert-link">"##));
+        assert!(html.contains(r##"<a href="#new-password-check" class="alert-link">"##));
+        assert!(html.contains(r#"aria-invalid="true" aria-describedby="new-password-feedback""#));
+        assert!(html.contains(r#"id="new-password-feedback""#));
+
+        let view = AddPasswordPartial {
+            locale: Locale::En,
+            field_errors: password_field_errors(Locale::En, true, vec![]),
+            suggestion: None,
+        };
+        let html = view.render().expect("Failed to render");
+        assert!(!html.contains("kanidm-error-summary"));
+        assert!(!html.contains(r#"aria-invalid="true""#));
+    }
+}
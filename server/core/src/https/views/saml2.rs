@@ -0,0 +1,290 @@
+use crate::https::{
+    extractors::{DomainInfo, DomainInfoRead, VerifiedClientInformation},
+    middleware::KOpId,
+    ServerState,
+};
+use kanidmd_lib::idm::saml2::Saml2SsoResponse;
+use kanidmd_lib::prelude::*;
+
+use kanidm_proto::internal::COOKIE_SAML2_REQ;
+
+use std::io::Read;
+
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Redirect, Response},
+    Extension, Form,
+};
+use axum_extra::extract::cookie::{CookieJar, SameSite};
+use flate2::read::DeflateDecoder;
+use serde::{Deserialize, Serialize};
+
+use super::constants::Urls;
+use super::login::{LoginDisplayCtx, Oauth2Ctx};
+use super::{cookies, UnrecoverableErrorView};
+
+/// The largest `AuthnRequest` we will inflate. Real requests are a few kilobytes.
+const SAML2_REQUEST_MAX_LEN: u64 = 64 * 1024;
+
+#[derive(Template)]
+#[template(path = "saml2_post.html")]
+struct Saml2PostView {
+    acs_url: String,
+    saml_response: String,
+    relay_state: Option<String>,
+    csp_nonce: String,
+}
+
+#[derive(Template)]
+#[template(path = "oauth2_access_denied.html")]
+struct AccessDeniedView {
+    operation_id: Uuid,
+}
+
+#[derive(Deserialize)]
+pub struct Saml2RequestParams {
+    #[serde(rename = "SAMLRequest")]
+    saml_request: String,
+    #[serde(rename = "RelayState")]
+    relay_state: Option<String>,
+}
+
+/// A request that is answered once the person has authenticated.
+#[derive(Serialize, Deserialize)]
+struct Saml2ResumeRequest {
+    sp_name: String,
+    saml_request: String,
+    relay_state: Option<String>,
+}
+
+fn saml2_decode(saml_request: &str, deflated: bool) -> Option<String> {
+    // The POST binding may wrap the encoded request over many lines.
+    let encoded: String = saml_request
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    let decoded = openssl::base64::decode_block(&encoded)
+        .inspect_err(|err| warn!(?err, "Invalid base64 in SAMLRequest"))
+        .ok()?;
+
+    if deflated {
+        let mut inflated = String::new();
+        DeflateDecoder::new(decoded.as_slice())
+            .take(SAML2_REQUEST_MAX_LEN)
+            .read_to_string(&mut inflated)
+            .inspect_err(|err| warn!(?err, "Unable to inflate SAMLRequest"))
+            .ok()?;
+        Some(inflated)
+    } else {
+        String::from_utf8(decoded)
+            .inspect_err(|err| warn!(?err, "Invalid utf8 in SAMLRequest"))
+            .ok()
+    }
+}
+
+fn invalid_request(kopid: &KOpId, domain_info: DomainInfoRead, jar: CookieJar) -> Response {
+    (
+        jar,
+        UnrecoverableErrorView {
+            err_code: OperationError::SA0001AuthnRequestInvalid,
+            operation_id: kopid.eventid,
+            locale: kopid.locale,
+            domain_info,
+        },
+    )
+        .into_response()
+}
+
+/// The HTTP-Redirect binding. The bearer cookie is sent with this top level navigation, so
+/// the request can be answered straight away.
+pub async fn view_sso_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Path(sp_name): Path<String>,
+    jar: CookieJar,
+    Query(params): Query<Saml2RequestParams>,
+) -> Response {
+    let Some(saml_request) = saml2_decode(&params.saml_request, true) else {
+        return invalid_request(&kopid, domain_info, jar);
+    };
+
+    saml2_sso(
+        state,
+        kopid,
+        client_auth_info,
+        domain_info,
+        jar,
+        Saml2ResumeRequest {
+            sp_name,
+            saml_request,
+            relay_state: params.relay_state,
+        },
+    )
+    .await
+}
+
+/// The HTTP-POST binding. This is a cross site post, so the bearer cookie is not sent with it.
+/// The request is stored and resumed from a top level navigation that does carry the cookie.
+pub async fn view_sso_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    DomainInfo(domain_info): DomainInfo,
+    Path(sp_name): Path<String>,
+    jar: CookieJar,
+    Form(params): Form<Saml2RequestParams>,
+) -> Response {
+    let Some(saml_request) = saml2_decode(&params.saml_request, false) else {
+        return invalid_request(&kopid, domain_info, jar);
+    };
+
+    let resume_req = Saml2ResumeRequest {
+        sp_name,
+        saml_request,
+        relay_state: params.relay_state,
+    };
+
+    match resume_cookie(&state, &jar, &resume_req) {
+        Some(new_jar) => (new_jar, Redirect::to(Urls::Saml2Resume.as_ref())).into_response(),
+        None => invalid_request(&kopid, domain_info, jar),
+    }
+}
+
+pub async fn view_resume_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    jar: CookieJar,
+) -> Response {
+    let maybe_resume_req =
+        cookies::get_signed::<Saml2ResumeRequest>(&state, &jar, COOKIE_SAML2_REQ);
+
+    // No matter what, we always clear the stored saml2 cookie to prevent
+    // ui loops
+    let jar = cookies::destroy(jar, COOKIE_SAML2_REQ, &state);
+
+    let Some(resume_req) = maybe_resume_req else {
+        error!("unable to resume session, no valid saml2 request was found in the cookie. This cookie has been removed.");
+        return (
+            jar,
+            UnrecoverableErrorView {
+                err_code: OperationError::UI0004InvalidSaml2Resume,
+                operation_id: kopid.eventid,
+                locale: kopid.locale,
+                domain_info,
+            },
+        )
+            .into_response();
+    };
+
+    saml2_sso(state, kopid, client_auth_info, domain_info, jar, resume_req).await
+}
+
+fn resume_cookie(
+    state: &ServerState,
+    jar: &CookieJar,
+    resume_req: &Saml2ResumeRequest,
+) -> Option<CookieJar> {
+    cookies::make_signed(state, COOKIE_SAML2_REQ, resume_req).map(|mut cookie| {
+        // This must be sent on the top level navigation that follows a cross site post.
+        cookie.set_same_site(SameSite::Lax);
+        // Expire at the end of the session.
+        cookie.set_expires(None);
+        cookie.set_max_age(time::Duration::minutes(15));
+        jar.clone().add(cookie)
+    })
+}
+
+async fn saml2_sso(
+    state: ServerState,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+    domain_info: DomainInfoRead,
+    jar: CookieJar,
+    resume_req: Saml2ResumeRequest,
+) -> Response {
+    let res = state
+        .qe_r_ref
+        .handle_saml2_sso(
+            client_auth_info,
+            resume_req.sp_name.clone(),
+            resume_req.saml_request.clone(),
+            resume_req.relay_state.clone(),
+            kopid.eventid,
+        )
+        .await;
+
+    match res {
+        Ok(Saml2SsoResponse::Permitted {
+            acs_url,
+            saml_response,
+            relay_state,
+        }) => (
+            jar,
+            Saml2PostView {
+                acs_url: acs_url.to_string(),
+                saml_response,
+                relay_state,
+                csp_nonce: kopid.csp_nonce.clone(),
+            },
+        )
+            .into_response(),
+        Ok(Saml2SsoResponse::AuthenticationRequired { client_name }) => {
+            // Sign the request and hide it in our cookie - we'll come back for
+            // you later.
+            match resume_cookie(&state, &jar, &resume_req) {
+                Some(new_jar) => {
+                    let display_ctx = LoginDisplayCtx {
+                        domain_info,
+                        locale: kopid.locale,
+                        oauth2: Some(Oauth2Ctx { client_name }),
+                        reauth: None,
+                        error: None,
+                    };
+
+                    super::login::view_oauth2_get(&state, new_jar, display_ctx, None)
+                }
+                None => (
+                    jar,
+                    UnrecoverableErrorView {
+                        err_code: OperationError::InvalidSessionState,
+                        operation_id: kopid.eventid,
+                        locale: kopid.locale,
+                        domain_info,
+                    },
+                )
+                    .into_response(),
+            }
+        }
+        Err(OperationError::AccessDenied) => (
+            jar,
+            AccessDeniedView {
+                operation_id: kopid.eventid,
+            },
+        )
+            .into_response(),
+        // Never redirect to the service provider in an error condition, the request may
+        // not have come from it.
+        Err(err_code) => {
+            error!(
+                "Unable to answer SAML request - Error ID: {:?} error: {:?}",
+                kopid.eventid, err_code
+            );
+
+            (
+                jar,
+                UnrecoverableErrorView {
+                    err_code,
+                    operation_id: kopid.eventid,
+                    locale: kopid.locale,
+                    domain_info,
+                },
+            )
+                .into_response()
+        }
+    }
+}
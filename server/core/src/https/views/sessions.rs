@@ -0,0 +1,99 @@
+use askama::Template;
+use askama_axum::IntoResponse;
+
+use axum::extract::State;
+use axum::response::Response;
+use axum::{Extension, Form};
+
+use kanidm_proto::internal::{SelfOauth2Session, SelfSession};
+use kanidmd_lib::idm::server::DomainInfoRead;
+use kanidmd_lib::idm::ClientAuthInfo;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::constants::{ProfileMenuItems, Urls};
+use super::errors::HtmxError;
+use super::navbar::NavbarCtx;
+use crate::https::extractors::{DomainInfo, VerifiedClientInformation};
+use crate::https::middleware::KOpId;
+use crate::https::ServerState;
+
+#[derive(Template)]
+#[template(path = "user_settings.html")]
+struct ProfileView {
+    navbar_ctx: NavbarCtx,
+    profile_partial: SessionsPartialView,
+}
+
+#[derive(Template)]
+#[template(path = "user_settings_sessions_partial.html")]
+struct SessionsPartialView {
+    menu_active_item: ProfileMenuItems,
+    sessions: Vec<SelfSession>,
+    oauth2_sessions: Vec<SelfOauth2Session>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SessionRevokeForm {
+    session_id: Uuid,
+}
+
+pub(crate) async fn view_sessions_get(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+) -> axum::response::Result<Response> {
+    let self_sessions = state
+        .qe_r_ref
+        .handle_list_self_sessions(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    Ok(ProfileView {
+        navbar_ctx: NavbarCtx { domain_info },
+
+        profile_partial: SessionsPartialView {
+            menu_active_item: ProfileMenuItems::Sessions,
+            sessions: self_sessions.sessions,
+            oauth2_sessions: self_sessions.oauth2_sessions,
+        },
+    }
+    .into_response())
+}
+
+pub(crate) async fn view_session_revoke_post(
+    State(state): State<ServerState>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    DomainInfo(domain_info): DomainInfo,
+    Form(form): Form<SessionRevokeForm>,
+) -> axum::response::Result<Response> {
+    state
+        .qe_w_ref
+        .handle_self_session_revoke(client_auth_info.clone(), form.session_id, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info.clone()))?;
+
+    render_sessions_partial(state, kopid, client_auth_info, domain_info).await
+}
+
+async fn render_sessions_partial(
+    state: ServerState,
+    kopid: KOpId,
+    client_auth_info: ClientAuthInfo,
+    domain_info: DomainInfoRead,
+) -> axum::response::Result<Response> {
+    let self_sessions = state
+        .qe_r_ref
+        .handle_list_self_sessions(client_auth_info, kopid.eventid)
+        .await
+        .map_err(|op_err| HtmxError::new(&kopid, op_err, domain_info))?;
+
+    Ok(SessionsPartialView {
+        menu_active_item: ProfileMenuItems::Sessions,
+        sessions: self_sessions.sessions,
+        oauth2_sessions: self_sessions.oauth2_sessions,
+    }
+    .into_response())
+}
@@ -4,6 +4,7 @@
 use std::fs;
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use chrono::Utc;
 use cron::Schedule;
@@ -11,31 +12,158 @@ use cron::Schedule;
 use tokio::sync::broadcast;
 use tokio::time::{interval, sleep, Duration, MissedTickBehavior};
 
-use crate::config::OnlineBackup;
+use crate::audit::AuditStore;
+use crate::backup::S3BackupStore;
+use crate::config::{AuditConfig, ChangelogConfig, OnlineBackup};
 use crate::CoreAction;
 
 use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
-use kanidmd_lib::constants::PURGE_FREQUENCY;
+use kanidmd_lib::constants::AUDIT_EXPORT_FREQUENCY;
 use kanidmd_lib::event::{OnlineBackupEvent, PurgeRecycledEvent, PurgeTombstoneEvent};
 
+/// Parse a cron schedule. Both the standard syntax of five fields, and the extended syntax that
+/// includes seconds and years are accepted. `what` names the schedule in any errors.
+pub(crate) fn parse_schedule(crono_expr: &str, what: &str) -> Result<Schedule, ()> {
+    let mut crono_expr_values = crono_expr.split_ascii_whitespace().collect::<Vec<&str>>();
+    let chrono_expr_uses_standard_syntax = crono_expr_values.len() == 5;
+    if chrono_expr_uses_standard_syntax {
+        // we add a 0 element at the beginning to simulate the standard crono syntax which always runs
+        // commands at seconds 00
+        crono_expr_values.insert(0, "0");
+        crono_expr_values.push("*");
+    }
+    let crono_expr_schedule = crono_expr_values.join(" ");
+    if chrono_expr_uses_standard_syntax {
+        info!(
+            "Provided {} schedule is: {}, now being transformed to: {}",
+            what.to_lowercase(),
+            crono_expr,
+            crono_expr_schedule
+        );
+    }
+    // Cron expression handling
+    let cron_expr = Schedule::from_str(crono_expr_schedule.as_str()).map_err(|e| {
+        error!("{} schedule parse error: {}", what, e);
+        error!("valid formats are:");
+        error!("sec  min   hour   day of month   month   day of week   year");
+        error!("min   hour   day of month   month   day of week");
+        error!("@hourly | @daily | @weekly");
+    })?;
+
+    info!("{} schedule parsed as: {}", what, cron_expr);
+
+    if cron_expr.upcoming(Utc).next().is_none() {
+        error!(
+            "{} schedule error: '{}' will not match any date.",
+            what, cron_expr
+        );
+        return Err(());
+    }
+
+    Ok(cron_expr)
+}
+
 pub(crate) struct IntervalActor;
 
+/// Where online backups are written to.
+enum BackupDestination {
+    Path(String),
+    S3(Box<S3BackupStore>),
+}
+
+/// An online backup configuration that has been checked, and is ready to be started. This
+/// allows a reloaded configuration to be checked before the running backups are replaced.
+pub(crate) struct OnlineBackupPlan {
+    cron_expr: Schedule,
+    destination: BackupDestination,
+    versions: usize,
+}
+
+impl OnlineBackupPlan {
+    // Allow this because result is the only way to map and ? to bubble up, but we aren't
+    // returning an op-error here because this is in early start up.
+    #[allow(clippy::result_unit_err)]
+    pub fn new(online_backup_config: &OnlineBackup) -> Result<Self, ()> {
+        let versions = online_backup_config.versions;
+        let cron_expr = parse_schedule(&online_backup_config.schedule, "Online backup")?;
+
+        let destination = match (&online_backup_config.s3, &online_backup_config.path) {
+            (Some(s3_config), _) => {
+                let store = S3BackupStore::new(s3_config).map_err(|e| {
+                    error!(?e, "Online backup failed to configure the object store");
+                })?;
+                info!(
+                    "Online backups will be sent to bucket '{}' at {}",
+                    s3_config.bucket, s3_config.endpoint
+                );
+                BackupDestination::S3(Box::new(store))
+            }
+            (None, Some(outpath)) => {
+                // Output path handling
+                let op = Path::new(outpath);
+
+                // does the path exist and is a directory?
+                if !op.exists() {
+                    info!(
+                        "Online backup output folder '{}' does not exist, trying to create it.",
+                        outpath
+                    );
+                    fs::create_dir_all(outpath).map_err(|e| {
+                        error!(
+                            "Online backup failed to create output directory '{}': {}",
+                            outpath, e
+                        )
+                    })?;
+                }
+
+                if !op.is_dir() {
+                    error!("Online backup output '{}' is not a directory or we are missing permissions to access it.", outpath);
+                    return Err(());
+                }
+
+                BackupDestination::Path(outpath.clone())
+            }
+            (None, None) => {
+                error!("Online backup output path is not set.");
+                return Err(());
+            }
+        };
+
+        Ok(OnlineBackupPlan {
+            cron_expr,
+            destination,
+            versions,
+        })
+    }
+}
+
 impl IntervalActor {
     pub fn start(
         server: &'static QueryServerWriteV1,
+        read_only_replica: bool,
+        changelog: &ChangelogConfig,
         mut rx: broadcast::Receiver<CoreAction>,
     ) -> tokio::task::JoinHandle<()> {
+        let purge_interval = changelog.purge_interval();
+        let compact = changelog.compact;
+
         tokio::spawn(async move {
-            let mut inter = interval(Duration::from_secs(PURGE_FREQUENCY));
+            let mut inter = interval(purge_interval);
             inter.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
             loop {
                 server
-                    .handle_purgetombstoneevent(PurgeTombstoneEvent::new())
+                    .handle_purgetombstoneevent(PurgeTombstoneEvent::new(), compact)
                     .await;
                 server
                     .handle_purgerecycledevent(PurgeRecycledEvent::new())
                     .await;
+                // Lifecycle policies and lapsed group memberships are applied by the writable
+                // servers, and the changes are replicated to read only replicas.
+                if !read_only_replica {
+                    server.handle_account_lifecycle().await;
+                    server.handle_purge_lapsed_group_members().await;
+                }
 
                 tokio::select! {
                     Ok(action) = rx.recv() => {
@@ -54,81 +182,18 @@ impl IntervalActor {
         })
     }
 
-    // Allow this because result is the only way to map and ? to bubble up, but we aren't
-    // returning an op-error here because this is in early start up.
-    #[allow(clippy::result_unit_err)]
     pub fn start_online_backup(
         server: &'static QueryServerReadV1,
-        online_backup_config: &OnlineBackup,
+        plan: OnlineBackupPlan,
         mut rx: broadcast::Receiver<CoreAction>,
-    ) -> Result<tokio::task::JoinHandle<()>, ()> {
-        let outpath = match online_backup_config.path.to_owned() {
-            Some(val) => val,
-            None => {
-                error!("Online backup output path is not set.");
-                return Err(());
-            }
-        };
-        let versions = online_backup_config.versions;
-        let crono_expr = online_backup_config.schedule.as_str().to_string();
-        let mut crono_expr_values = crono_expr.split_ascii_whitespace().collect::<Vec<&str>>();
-        let chrono_expr_uses_standard_syntax = crono_expr_values.len() == 5;
-        if chrono_expr_uses_standard_syntax {
-            // we add a 0 element at the beginning to simulate the standard crono syntax which always runs
-            // commands at seconds 00
-            crono_expr_values.insert(0, "0");
-            crono_expr_values.push("*");
-        }
-        let crono_expr_schedule = crono_expr_values.join(" ");
-        if chrono_expr_uses_standard_syntax {
-            info!(
-                "Provided online backup schedule is: {}, now being transformed to: {}",
-                crono_expr, crono_expr_schedule
-            );
-        }
-        // Cron expression handling
-        let cron_expr = Schedule::from_str(crono_expr_schedule.as_str()).map_err(|e| {
-            error!("Online backup schedule parse error: {}", e);
-            error!("valid formats are:");
-            error!("sec  min   hour   day of month   month   day of week   year");
-            error!("min   hour   day of month   month   day of week");
-            error!("@hourly | @daily | @weekly");
-        })?;
-
-        info!("Online backup schedule parsed as: {}", cron_expr);
-
-        if cron_expr.upcoming(Utc).next().is_none() {
-            error!(
-                "Online backup schedule error: '{}' will not match any date.",
-                cron_expr
-            );
-            return Err(());
-        }
-
-        // Output path handling
-        let op = Path::new(&outpath);
-
-        // does the path exist and is a directory?
-        if !op.exists() {
-            info!(
-                "Online backup output folder '{}' does not exist, trying to create it.",
-                outpath
-            );
-            fs::create_dir_all(&outpath).map_err(|e| {
-                error!(
-                    "Online backup failed to create output directory '{}': {}",
-                    outpath.clone(),
-                    e
-                )
-            })?;
-        }
-
-        if !op.is_dir() {
-            error!("Online backup output '{}' is not a directory or we are missing permissions to access it.", outpath);
-            return Err(());
-        }
+    ) -> tokio::task::JoinHandle<()> {
+        let OnlineBackupPlan {
+            cron_expr,
+            destination,
+            versions,
+        } = plan;
 
-        let handle = tokio::spawn(async move {
+        tokio::spawn(async move {
             for next_time in cron_expr.upcoming(Utc) {
                 // We add 1 second to the `wait_time` in order to get "even" timestampes
                 // for example: 1 + 17:05:59Z --> 17:06:00Z
@@ -145,20 +210,90 @@ impl IntervalActor {
                         }
                     }
                     _ = sleep(Duration::from_secs(wait_seconds)) => {
-                        if let Err(e) = server
-                            .handle_online_backup(
-                                OnlineBackupEvent::new(),
-                                outpath.clone().as_str(),
-                                versions,
-                            )
-                            .await
-                        {
+                        let res = match &destination {
+                            BackupDestination::Path(outpath) => {
+                                server
+                                    .handle_online_backup(
+                                        OnlineBackupEvent::new(),
+                                        outpath.as_str(),
+                                        versions,
+                                    )
+                                    .await
+                            }
+                            BackupDestination::S3(store) => {
+                                server
+                                    .handle_online_backup_s3(OnlineBackupEvent::new(), store, versions)
+                                    .await
+                            }
+                        };
+                        if let Err(e) = res {
                             error!(?e, "An online backup error occurred.");
                         }
                     }
                 }
             }
             info!("Stopped {}", super::TaskName::BackupActor);
+        })
+    }
+
+    // Allow this because result is the only way to map and ? to bubble up, but we aren't
+    // returning an op-error here because this is in early start up.
+    #[allow(clippy::result_unit_err)]
+    pub fn start_audit_export(
+        audit: Arc<AuditStore>,
+        audit_config: &AuditConfig,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> Result<tokio::task::JoinHandle<()>, ()> {
+        if let Some(outpath) = &audit_config.export_path {
+            let op = Path::new(outpath);
+
+            if !op.exists() {
+                info!(
+                    "Audit export folder '{}' does not exist, trying to create it.",
+                    outpath
+                );
+                fs::create_dir_all(op).map_err(|e| {
+                    error!(
+                        "Audit export failed to create output directory '{}': {}",
+                        outpath, e
+                    )
+                })?;
+            }
+
+            if !op.is_dir() {
+                error!("Audit export output '{}' is not a directory or we are missing permissions to access it.", outpath);
+                return Err(());
+            }
+        } else {
+            warn!("No audit export path set, audit events will be discarded once they leave the hot tier.");
+        }
+
+        let handle = tokio::spawn(async move {
+            let mut inter = interval(Duration::from_secs(AUDIT_EXPORT_FREQUENCY));
+            inter.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                        }
+                    }
+                    _ = inter.tick() => {
+                        let now = time::OffsetDateTime::now_utc();
+                        match audit.export_expired(now) {
+                            Ok(0) => {}
+                            Ok(count) => {
+                                info!("Removed {} audit events from the hot tier", count);
+                            }
+                            Err(e) => {
+                                error!(?e, "An audit export error occurred.");
+                            }
+                        }
+                    }
+                }
+            }
+            info!("Stopped {}", super::TaskName::AuditExportActor);
         });
 
         Ok(handle)
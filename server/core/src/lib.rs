@@ -26,26 +26,48 @@ extern crate tracing;
 #[macro_use]
 extern crate kanidmd_lib;
 
+mod acme;
 mod actors;
 pub mod admin;
+mod audit;
+mod backup;
 pub mod config;
 mod crypto;
+mod doctor;
+mod emaillink;
+pub mod embedded;
+mod hooks;
 mod https;
 mod interval;
 mod ldaps;
+mod mail;
+mod notify;
 mod repl;
+mod report;
+mod tenant;
 mod utils;
+mod webhook;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use crate::utils::touch_file_or_quit;
 use compact_jwt::{JwsHs256Signer, JwsSigner};
-use kanidm_proto::internal::OperationError;
-use kanidmd_lib::be::{Backend, BackendConfig, BackendTransaction};
+use kanidm_proto::internal::{OperationError, SelfTestReport, SelfTestStatus};
+use kanidmd_lib::be::{Backend, BackendConfig, BackendTransaction, KeyEncryptionKey};
+use kanidmd_lib::credential::breach::PasswordBreachFilter;
+use kanidmd_lib::idm::audit::AuditEvent;
+use kanidmd_lib::idm::breakglass::BreakGlassCodeFile;
+use kanidmd_lib::idm::geoip::{GeoIp, GeoIpDatabase};
 use kanidmd_lib::idm::ldap::LdapServer;
 use kanidmd_lib::prelude::*;
 use kanidmd_lib::schema::Schema;
+use kanidmd_lib::server::verify::{VerifyIssue, VerifyRepair};
 use kanidmd_lib::status::StatusActor;
 use kanidmd_lib::value::CredentialType;
 #[cfg(not(target_family = "windows"))]
@@ -55,11 +77,22 @@ use tokio::sync::broadcast;
 use tokio::sync::Notify;
 use tokio::task;
 
+use crate::acme::AcmeActor;
 use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
 use crate::admin::AdminActor;
-use crate::config::{Configuration, ServerRole};
-use crate::interval::IntervalActor;
-use tokio::sync::mpsc;
+use crate::audit::AuditStore;
+use crate::backup::{read_sealed_backup, BACKUP_OBJECT_SUFFIX};
+use crate::config::{Configuration, GeoIpConfig, SelfTestSeverity, ServerRole};
+use crate::emaillink::EmailLinkActor;
+use crate::embedded::EmbeddedClient;
+use crate::hooks::HookActor;
+use crate::interval::{IntervalActor, OnlineBackupPlan};
+use crate::mail::Mailer;
+use crate::notify::NotificationActor;
+use crate::report::ReportActor;
+use crate::tenant::TenantServer;
+use crate::webhook::WebhookActor;
+use tokio::sync::{mpsc, oneshot};
 
 // === internal setup helpers
 
@@ -67,6 +100,33 @@ fn setup_backend(config: &Configuration, schema: &Schema) -> Result<Backend, Ope
     setup_backend_vacuum(config, schema, false)
 }
 
+/// Read the key that wraps the data encryption keys of the database. This is a 32 byte key,
+/// encoded as hex.
+fn load_data_encryption_key(path: &str) -> Result<KeyEncryptionKey, OperationError> {
+    let key_hex = std::fs::read_to_string(path).map_err(|err| {
+        error!(?err, %path, "Unable to read data encryption key");
+        OperationError::FsError
+    })?;
+
+    hex::decode(key_hex.trim())
+        .ok()
+        .and_then(|key| KeyEncryptionKey::try_from(key).ok())
+        .ok_or_else(|| {
+            error!(%path, "Data encryption key must be 32 bytes encoded as hex");
+            OperationError::InvalidState
+        })
+}
+
+/// Read the password of the PostgreSQL database user.
+fn load_db_password(path: &str) -> Result<String, OperationError> {
+    std::fs::read_to_string(path)
+        .map(|password| password.trim().to_string())
+        .map_err(|err| {
+            error!(?err, %path, "Unable to read database password");
+            OperationError::FsError
+        })
+}
+
 fn setup_backend_vacuum(
     config: &Configuration,
     schema: &Schema,
@@ -79,13 +139,30 @@ fn setup_backend_vacuum(
 
     let pool_size: u32 = config.threads as u32;
 
-    let cfg = BackendConfig::new(
+    let mut cfg = BackendConfig::new(
         config.db_path.as_str(),
         pool_size,
         config.db_fs_type.unwrap_or_default(),
         config.db_arc_size,
     );
 
+    if let Some(url) = config.db_url.as_deref() {
+        let password = config
+            .db_password_path
+            .as_deref()
+            .map(load_db_password)
+            .transpose()?;
+        cfg.set_postgres(url, password);
+    }
+
+    if let Some(path) = config.data_encryption_key.as_deref() {
+        cfg.set_data_encryption_key(load_data_encryption_key(path)?);
+    }
+
+    if let Some(threshold) = config.slow_search_threshold {
+        cfg.set_slow_search_threshold(threshold);
+    }
+
     Backend::new(cfg, idxmeta, vacuum)
 }
 
@@ -101,6 +178,7 @@ async fn setup_qs_idms(
     let curtime = duration_from_epoch_now();
     // Create a query_server implementation
     let query_server = QueryServer::new(be, schema, config.domain.clone(), curtime)?;
+    query_server.set_retention(config.changelog.retention());
 
     // TODO #62: Should the IDM parts be broken out to the IdmServer?
     // What's important about this initial setup here is that it also triggers
@@ -130,6 +208,7 @@ async fn setup_qs(
     let curtime = duration_from_epoch_now();
     // Create a query_server implementation
     let query_server = QueryServer::new(be, schema, config.domain.clone(), curtime)?;
+    query_server.set_retention(config.changelog.retention());
 
     // TODO #62: Should the IDM parts be broken out to the IdmServer?
     // What's important about this initial setup here is that it also triggers
@@ -371,7 +450,40 @@ pub fn backup_server_core(config: &Configuration, dst_path: &str) {
     // Let the txn abort, even on success.
 }
 
-pub async fn restore_server_core(config: &Configuration, dst_path: &str) {
+/// Read the content of a backup file, decrypting it if it was sent to an object store.
+fn read_backup_file(config: &Configuration, path: &str) -> Result<String, OperationError> {
+    if path.ends_with(BACKUP_OBJECT_SUFFIX) {
+        // An encrypted backup from an object store.
+        let Some(s3_config) = config
+            .online_backup
+            .as_ref()
+            .and_then(|online_backup| online_backup.s3.as_ref())
+        else {
+            error!("Unable to restore an encrypted backup, online_backup.s3.encryption_key_path is not configured");
+            return Err(OperationError::InvalidState);
+        };
+
+        read_sealed_backup(path, &s3_config.encryption_key_path)
+    } else {
+        std::fs::read_to_string(path).map_err(|e| {
+            error!(?e, ?path, "Unable to read backup");
+            OperationError::FsError
+        })
+    }
+}
+
+/// How the content of a backup should be applied to the database.
+pub enum RestoreMode<'a> {
+    /// Replace the database with the content of the backup.
+    Full,
+    /// Replace the database with its state at the given RFC3339 time, by replaying the
+    /// changes of the backup over an earlier base backup.
+    Until { base_path: &'a str, until: &'a str },
+    /// Restore only the named entry, and any deleted entries that it references.
+    Entry(&'a str),
+}
+
+pub async fn restore_server_core(config: &Configuration, dst_path: &str, mode: RestoreMode<'_>) {
     touch_file_or_quit(config.db_path.as_str());
 
     // First, we provide the in-memory schema so that core attrs are indexed correctly.
@@ -391,6 +503,47 @@ pub async fn restore_server_core(config: &Configuration, dst_path: &str) {
         }
     };
 
+    let backup = match read_backup_file(config, dst_path) {
+        Ok(backup) => backup,
+        Err(e) => {
+            error!("Failed to read backup: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let RestoreMode::Entry(target) = mode {
+        // The rest of the database is retained, so this needs a working query server.
+        let qs = match setup_qs(be, schema, config).await {
+            Ok(t) => t,
+            Err(e) => {
+                error!("Unable to setup query server -> {:?}", e);
+                std::process::exit(1);
+            }
+        };
+
+        let Ok(mut qs_write) = qs.write(duration_from_epoch_now()).await else {
+            error!("Unable to acquire write transaction");
+            std::process::exit(1);
+        };
+
+        match qs_write
+            .restore_entry_from_backup(&backup, target)
+            .and_then(|restored| qs_write.commit().map(|_| restored))
+        {
+            Ok(restored) => {
+                for uuid in restored {
+                    info!(?uuid, "restored");
+                }
+                info!("✅ Restore Success!");
+            }
+            Err(e) => {
+                error!("Failed to restore entry - Rollback has occurred: {:?}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let mut be_wr_txn = match be.write() {
         Ok(txn) => txn,
         Err(err) => {
@@ -401,7 +554,30 @@ pub async fn restore_server_core(config: &Configuration, dst_path: &str) {
             return;
         }
     };
-    let r = be_wr_txn.restore(dst_path).and_then(|_| be_wr_txn.commit());
+
+    let r = match mode {
+        RestoreMode::Until { base_path, until } => {
+            let until = match time::OffsetDateTime::parse(
+                until,
+                &time::format_description::well_known::Rfc3339,
+            ) {
+                Ok(odt) => {
+                    let odt = odt.to_offset(time::UtcOffset::UTC);
+                    Duration::from_secs(odt.unix_timestamp().max(0) as u64)
+                        + Duration::from_nanos(odt.nanosecond() as u64)
+                }
+                Err(e) => {
+                    error!(?e, "Invalid restore time, must be in RFC3339 format");
+                    std::process::exit(1);
+                }
+            };
+
+            read_backup_file(config, base_path)
+                .and_then(|base| be_wr_txn.restore_until(&base, &backup, until))
+        }
+        _ => be_wr_txn.restore_data(&backup),
+    }
+    .and_then(|_| be_wr_txn.commit());
 
     if r.is_err() {
         error!("Failed to restore database: {:?}", r);
@@ -414,6 +590,63 @@ pub async fn restore_server_core(config: &Configuration, dst_path: &str) {
     info!("✅ Restore Success!");
 }
 
+/// Copy the content of an SQLite database into the configured PostgreSQL database. The server
+/// uuid is kept, so replication partners see the same server after the migration.
+pub async fn migrate_sqlite_server_core(config: &Configuration, sqlite_path: &str) {
+    if config.db_url.is_none() {
+        error!("db_url must be configured to migrate the database to PostgreSQL");
+        std::process::exit(1);
+    }
+
+    let schema = match Schema::new() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Failed to setup in memory schema: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut sqlite_config = config.clone();
+    sqlite_config.update_db_path(sqlite_path);
+    sqlite_config.update_db_url(&None, &None);
+
+    // The backup format is the same for every storage option, so it is used to move the
+    // content between them.
+    let backup = match setup_backend(&sqlite_config, &schema)
+        .and_then(|be| be.read().and_then(|mut be_ro_txn| be_ro_txn.backup_data()))
+    {
+        Ok(backup) => backup,
+        Err(e) => {
+            error!("Failed to read the SQLite database: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let be = match setup_backend(config, &schema) {
+        Ok(be) => be,
+        Err(e) => {
+            error!("Failed to setup backend: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let r = be.write().and_then(|mut be_wr_txn| {
+        be_wr_txn
+            .restore_data(&backup)
+            .and_then(|_| be_wr_txn.commit())
+    });
+
+    if r.is_err() {
+        error!("Failed to migrate database: {:?}", r);
+        std::process::exit(1);
+    }
+    info!("Database copied successfully");
+
+    reindex_inner(be, schema, config).await;
+
+    info!("✅ Migration Success!");
+}
+
 pub async fn reindex_server_core(config: &Configuration) {
     info!("Start Index Phase 1 ...");
     // First, we provide the in-memory schema so that core attrs are indexed correctly.
@@ -576,7 +809,7 @@ pub async fn domain_rename_core(config: &Configuration) {
     };
 }
 
-pub async fn verify_server_core(config: &Configuration) {
+pub async fn verify_server_core(config: &Configuration, repair: bool) {
     let curtime = duration_from_epoch_now();
     // setup the qs - without initialise!
     let schema_mem = match Schema::new() {
@@ -595,7 +828,7 @@ pub async fn verify_server_core(config: &Configuration) {
         }
     };
 
-    let server = match QueryServer::new(be, schema_mem, config.domain.clone(), curtime) {
+    let server = match QueryServer::new(be.clone(), schema_mem, config.domain.clone(), curtime) {
         Ok(qs) => qs,
         Err(err) => {
             error!(?err, "Failed to setup query server");
@@ -604,19 +837,241 @@ pub async fn verify_server_core(config: &Configuration) {
     };
 
     // Run verifications.
-    let r = server.verify().await;
+    let issues = server.verify_report(curtime).await;
+    print_verify_issues(&issues);
 
-    if r.is_empty() {
+    if issues.is_empty() {
         eprintln!("Verification passed!");
         std::process::exit(0);
-    } else {
-        for er in r {
-            error!("{:?}", er);
+    }
+
+    let repairs: BTreeSet<VerifyRepair> = issues.iter().filter_map(|issue| issue.repair).collect();
+
+    if !repair {
+        let repairable = issues.iter().filter(|issue| issue.repair.is_some()).count();
+        eprintln!(
+            "Verification failed - {} issues found, {} of which can be fixed with --repair",
+            issues.len(),
+            repairable
+        );
+        std::process::exit(1);
+    }
+
+    if repairs.is_empty() {
+        eprintln!("Verification failed - none of the issues found can be safely repaired");
+        std::process::exit(1);
+    }
+
+    drop(server);
+
+    // Indexes must be rebuilt before the query server is used to search for anything else.
+    if repairs.contains(&VerifyRepair::Reindex) {
+        let mut be_wr_txn = match be.write() {
+            Ok(txn) => txn,
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Unable to proceed, backend write transaction failure."
+                );
+                std::process::exit(1);
+            }
+        };
+        if let Err(err) = be_wr_txn.reindex(true).and_then(|_| be_wr_txn.commit()) {
+            error!(?err, "Failed to reindex database");
+            std::process::exit(1);
         }
+    }
+
+    let schema = match Schema::new() {
+        Ok(s) => s,
+        Err(err) => {
+            error!(?err, "Failed to setup in memory schema");
+            std::process::exit(1);
+        }
+    };
+
+    let server = match setup_qs(be, schema, config).await {
+        Ok(qs) => qs,
+        Err(err) => {
+            error!(?err, "Unable to setup query server");
+            std::process::exit(1);
+        }
+    };
+
+    let curtime = duration_from_epoch_now();
+    let mut qs_write = match server.write(curtime).await {
+        Ok(txn) => txn,
+        Err(err) => {
+            error!(?err, "Unable to acquire write transaction");
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = qs_write
+        .verify_repair(&repairs)
+        .and_then(|_| qs_write.commit())
+    {
+        error!(?err, "Repair failed - Rollback has occurred");
         std::process::exit(1);
     }
 
-    // Now add IDM server verifications?
+    // Some checks stop at the first class of issue, so what remains may only now be visible.
+    let issues = server.verify_report(curtime).await;
+    print_verify_issues(&issues);
+
+    if issues.is_empty() {
+        eprintln!("Repair succeeded, verification passed!");
+        std::process::exit(0);
+    } else {
+        eprintln!(
+            "Repair completed, but {} issues remain. These must be resolved manually.",
+            issues.len()
+        );
+        std::process::exit(1);
+    }
+}
+
+fn print_verify_issues(issues: &[VerifyIssue]) {
+    for issue in issues {
+        let uuid = issue
+            .uuid
+            .map(|u| u.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        match issue.repair {
+            Some(repair) => error!(
+                category = %issue.category,
+                %uuid,
+                detail = %issue.detail,
+                "repairable: {}",
+                repair
+            ),
+            None => error!(
+                category = %issue.category,
+                %uuid,
+                detail = %issue.detail,
+                "requires manual intervention"
+            ),
+        }
+    }
+}
+
+/// Check the parts of the configuration that can only be verified once it's loaded, without
+/// opening the database or starting any listeners.
+pub fn config_validate_core(config: &Configuration) -> Result<(), String> {
+    if let Err(err) = Url::parse(&config.origin) {
+        return Err(format!(
+            "Unable to parse origin URL {:?} - {:?}",
+            config.origin, err
+        ));
+    }
+
+    config.validate_role()?;
+    config.validate_changelog()?;
+    config.validate_hooks()?;
+    config.validate_tenants()?;
+
+    for (_, tls_config) in config.tenant_tls_configs() {
+        crypto::check_tls_material(&tls_config)?;
+    }
+
+    if let Some(geoip) = &config.geoip {
+        load_geoip(geoip)?;
+    }
+
+    match &config.tls_config {
+        Some(tls_config) => crypto::check_tls_material(tls_config),
+        None => Err("TLS chain and key must be configured".to_string()),
+    }
+}
+
+/// Load the GeoIP databases that addresses are located with.
+pub(crate) fn load_geoip(config: &GeoIpConfig) -> Result<GeoIp, String> {
+    let load = |path: &str| {
+        let data = std::fs::read(path).map_err(|e| format!("Unable to read {} - {:?}", path, e))?;
+        GeoIpDatabase::from_bytes(data)
+            .map_err(|e| format!("Invalid GeoIP database {} - {:?}", path, e))
+    };
+
+    let location_db = load(&config.database)?;
+    let asn_db = config.asn_database.as_deref().map(load).transpose()?;
+
+    Ok(GeoIp::new(
+        location_db,
+        asn_db,
+        config.max_travel_speed_kmh,
+        config.step_up,
+    ))
+}
+
+/// Check the environment that the server runs in, such as file permissions, the TLS chain, the
+/// system clock, the database and the reachability of replication partners. The findings are
+/// returned with the most severe first.
+pub async fn doctor_core(config: &Configuration, config_files: &[PathBuf]) -> SelfTestReport {
+    doctor::run_checks(config, config_files).await
+}
+
+/// Build the bloom filter of breached passwords from a dataset of SHA-1 password hashes, one
+/// per line, such as the one published by Have I Been Pwned. Returns the number of hashes
+/// that were added.
+pub fn password_breach_filter_build_core(
+    input: &Path,
+    output: &Path,
+    false_positive_rate: f64,
+) -> Result<u64, String> {
+    let open_input = || {
+        File::open(input)
+            .map(BufReader::new)
+            .map_err(|e| format!("Unable to open {} - {:?}", input.display(), e))
+    };
+
+    // The filter is sized from the number of hashes, so the dataset is read twice.
+    let mut items: u64 = 0;
+    for line in open_input()?.lines() {
+        line.map_err(|e| format!("Unable to read {} - {:?}", input.display(), e))?;
+        items += 1;
+    }
+
+    let mut filter = PasswordBreachFilter::new(items, false_positive_rate);
+
+    for (line_number, line) in open_input()?.lines().enumerate() {
+        let line = line.map_err(|e| format!("Unable to read {} - {:?}", input.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        filter
+            .insert_hex_line(&line)
+            .map_err(|e| format!("Invalid hash on line {} - {:?}", line_number + 1, e))?;
+    }
+
+    let file = File::create(output)
+        .map_err(|e| format!("Unable to create {} - {:?}", output.display(), e))?;
+    filter
+        .write_to(BufWriter::new(file))
+        .map_err(|e| format!("Unable to write {} - {:?}", output.display(), e))?;
+
+    Ok(items)
+}
+
+/// Generate the recovery codes of an emergency account, replacing any codes it already has
+/// in the file. The codes are returned so that they can be displayed once.
+pub fn break_glass_codes_generate_core(
+    account: &str,
+    output: &Path,
+    count: usize,
+) -> Result<Vec<String>, String> {
+    let mut file = if output.exists() {
+        BreakGlassCodeFile::read(output)
+            .map_err(|e| format!("Unable to read {} - {:?}", output.display(), e))?
+    } else {
+        BreakGlassCodeFile::default()
+    };
+
+    let codes = file.generate(account, count);
+
+    file.write(output)
+        .map_err(|e| format!("Unable to write {} - {:?}", output.display(), e))?;
+
+    Ok(codes)
 }
 
 pub fn cert_generate_core(config: &Configuration) {
@@ -713,16 +1168,31 @@ pub enum CoreAction {
     Shutdown,
 }
 
+/// A request from the admin socket to reload the configuration. The configuration file is
+/// owned by the process that started the server, so it re-reads the file and applies it with
+/// [CoreHandle::reload_config], then responds with the settings that changed.
+pub struct ConfigReloadRequest {
+    pub respond: oneshot::Sender<Result<Vec<String>, String>>,
+}
+
 pub(crate) enum TaskName {
+    AcmeActor,
     AdminSocket,
     AuditdActor,
+    AuditExportActor,
     BackupActor,
     DelayedActionActor,
+    EmailLinkActor,
     HttpsServer,
     IntervalActor,
     LdapActor,
+    MetricsServer,
+    NotificationActor,
     Replication,
+    ReportActor,
     TlsAcceptorReload,
+    WebhookActor,
+    HookActor,
 }
 
 impl Display for TaskName {
@@ -731,15 +1201,23 @@ impl Display for TaskName {
             f,
             "{}",
             match self {
+                TaskName::AcmeActor => "ACME Actor",
                 TaskName::AdminSocket => "Admin Socket",
                 TaskName::AuditdActor => "Auditd Actor",
+                TaskName::AuditExportActor => "Audit Export Actor",
                 TaskName::BackupActor => "Backup Actor",
                 TaskName::DelayedActionActor => "Delayed Action Actor",
+                TaskName::EmailLinkActor => "Email Link Actor",
                 TaskName::HttpsServer => "HTTPS Server",
                 TaskName::IntervalActor => "Interval Actor",
                 TaskName::LdapActor => "LDAP Acceptor Actor",
+                TaskName::MetricsServer => "Metrics Server",
+                TaskName::NotificationActor => "Notification Actor",
                 TaskName::Replication => "Replication",
+                TaskName::ReportActor => "Report Actor",
                 TaskName::TlsAcceptorReload => "TlsAcceptor Reload Monitor",
+                TaskName::WebhookActor => "Webhook Actor",
+                TaskName::HookActor => "Hook Actor",
             }
         )
     }
@@ -749,8 +1227,20 @@ pub struct CoreHandle {
     clean_shutdown: bool,
     tx: broadcast::Sender<CoreAction>,
     tls_acceptor_reload_notify: Arc<Notify>,
+    /// The configuration the server is running with, which is updated on reload.
+    config: Configuration,
+    trust_x_forward_for: Arc<AtomicBool>,
+    /// The backup actor is stopped separately to the other tasks, so that it can be replaced
+    /// when the configuration is reloaded.
+    backup_tx: broadcast::Sender<CoreAction>,
+    backup_handle: Option<task::JoinHandle<()>>,
+    config_reload_rx: mpsc::Receiver<ConfigReloadRequest>,
     /// This stores a name for the handle, and the handle itself so we can tell which failed/succeeded at the end.
     handles: Vec<(TaskName, task::JoinHandle<()>)>,
+    qs: QueryServer,
+    idms: Arc<IdmServer>,
+    qe_r_ref: &'static QueryServerReadV1,
+    qe_w_ref: &'static QueryServerWriteV1,
 }
 
 impl CoreHandle {
@@ -764,6 +1254,11 @@ impl CoreHandle {
             return;
         }
 
+        if let Some(backup_handle) = self.backup_handle.take() {
+            let _ = self.backup_tx.send(CoreAction::Shutdown);
+            self.handles.push((TaskName::BackupActor, backup_handle));
+        }
+
         // Wait on the handles.
         while let Some((handle_name, handle)) = self.handles.pop() {
             if let Err(error) = handle.await {
@@ -777,6 +1272,95 @@ impl CoreHandle {
     pub async fn tls_acceptor_reload(&mut self) {
         self.tls_acceptor_reload_notify.notify_one()
     }
+
+    /// The configuration that the server is running with.
+    pub fn configuration(&self) -> &Configuration {
+        &self.config
+    }
+
+    /// Wait for a request from the admin socket to reload the configuration.
+    pub async fn config_reload_requested(&mut self) -> Option<ConfigReloadRequest> {
+        self.config_reload_rx.recv().await
+    }
+
+    /// Apply the settings of a reloaded configuration that can be changed while the server is
+    /// running. These are `trust_x_forward_for`, `online_backup` and the TLS certificate, which
+    /// is re-read from the same files. The `log_level` is applied by the caller, as it owns the
+    /// logging pipeline.
+    ///
+    /// The new settings are all checked before any are applied, so if the configuration is
+    /// invalid the server continues with its current configuration. On success this returns
+    /// the names of the settings that were changed.
+    pub async fn reload_config(&mut self, config: Configuration) -> Result<Vec<String>, String> {
+        if let Err(err) =
+            crypto::setup_tls_with_tenants(&config.tls_config, &config.tenant_tls_configs())
+        {
+            return Err(format!("Unable to load the TLS certificate: {}", err));
+        }
+
+        let backup_changed = config.online_backup != self.config.online_backup;
+        let backup_plan = match &config.online_backup {
+            Some(online_backup) if backup_changed && online_backup.enabled => {
+                Some(OnlineBackupPlan::new(online_backup).map_err(|()| {
+                    "The online_backup configuration is invalid, see the server log for details"
+                        .to_string()
+                })?)
+            }
+            _ => None,
+        };
+
+        let mut changed = Vec::new();
+
+        if config.log_level != self.config.log_level {
+            changed.push("log_level".to_string());
+        }
+
+        if config.trust_x_forward_for != self.config.trust_x_forward_for {
+            self.trust_x_forward_for
+                .store(config.trust_x_forward_for, Ordering::Relaxed);
+            changed.push("trust_x_forward_for".to_string());
+        }
+
+        if backup_changed {
+            // This waits for a backup that is in progress to complete.
+            if let Some(backup_handle) = self.backup_handle.take() {
+                let _ = self.backup_tx.send(CoreAction::Shutdown);
+                if let Err(err) = backup_handle.await {
+                    error!(?err, "Task {} failed to finish", TaskName::BackupActor);
+                }
+            }
+
+            self.backup_handle = backup_plan.map(|plan| {
+                IntervalActor::start_online_backup(self.qe_r_ref, plan, self.backup_tx.subscribe())
+            });
+            changed.push("online_backup".to_string());
+        }
+
+        if config.tls_config.is_some() {
+            self.tls_acceptor_reload_notify.notify_one();
+            changed.push("tls".to_string());
+        }
+
+        self.config = config;
+        Ok(changed)
+    }
+
+    /// The query server of this core, for direct access to the database when the server
+    /// is embedded in another application.
+    pub fn query_server(&self) -> &QueryServer {
+        &self.qs
+    }
+
+    /// The idm server of this core, for direct access to identity management operations
+    /// when the server is embedded in another application.
+    pub fn idm_server(&self) -> &Arc<IdmServer> {
+        &self.idms
+    }
+
+    /// An in-process client of this core. See [embedded] for details.
+    pub fn client(&self) -> EmbeddedClient {
+        EmbeddedClient::new(self.qe_r_ref, self.qe_w_ref)
+    }
 }
 
 impl Drop for CoreHandle {
@@ -789,12 +1373,106 @@ impl Drop for CoreHandle {
     }
 }
 
+/// Apply the actions that the idm server has delayed, such as recording the use of a
+/// credential, from the write actor.
+async fn delayed_action_loop(
+    mut idms_delayed: IdmServerDelayed,
+    server_write_ref: &'static QueryServerWriteV1,
+    read_only_replica: bool,
+    mut broadcast_rx: broadcast::Receiver<CoreAction>,
+) {
+    let mut buffer = Vec::with_capacity(DELAYED_ACTION_BATCH_SIZE);
+    loop {
+        tokio::select! {
+            added = idms_delayed.recv_many(&mut buffer) => {
+                if added == 0 {
+                    // Channel has closed, stop the task.
+                    break
+                }
+                if read_only_replica {
+                    debug!(added, "Discarding delayed actions on read only replica");
+                    buffer.clear();
+                    continue;
+                }
+                server_write_ref.handle_delayedaction(&mut buffer).await;
+            }
+            Ok(action) = broadcast_rx.recv() => {
+                match action {
+                    CoreAction::Shutdown => break,
+                }
+            }
+        }
+    }
+    info!("Stopped {}", TaskName::DelayedActionActor);
+}
+
+/// Record the audit events of the idm server, and forward entry changes to the webhooks and
+/// hooks.
+async fn auditd_loop(
+    mut idms_audit: IdmServerAudit,
+    audit_store: Arc<AuditStore>,
+    webhook_tx: mpsc::UnboundedSender<AuditEvent>,
+    hook_tx: Option<mpsc::UnboundedSender<AuditEvent>>,
+    mut broadcast_rx: broadcast::Receiver<CoreAction>,
+) {
+    loop {
+        tokio::select! {
+            Ok(action) = broadcast_rx.recv() => {
+                match action {
+                    CoreAction::Shutdown => break,
+                }
+            }
+            audit_event = idms_audit.audit_rx().recv() => {
+                let Some(audit_event) = audit_event else {
+                    // Channel has closed, stop the task.
+                    break
+                };
+
+                if matches!(
+                    audit_event,
+                    AuditEvent::EntriesCreated { .. }
+                        | AuditEvent::EntriesModified { .. }
+                        | AuditEvent::EntriesDeleted { .. }
+                ) {
+                    if webhook_tx.send(audit_event.clone()).is_err() {
+                        error!("Unable to submit event to webhook queue");
+                    }
+
+                    if let Some(hook_tx) = &hook_tx {
+                        if hook_tx.send(audit_event.clone()).is_err() {
+                            error!("Unable to submit event to hook queue");
+                        }
+                    }
+                }
+
+                let audit_record = idms_audit.audit_record(audit_event);
+
+                match serde_json::to_string(&audit_record) {
+                    Ok(audit_event) => {
+                        warn!(%audit_event);
+                        audit_store.write_sinks(&audit_event);
+                    }
+                    Err(e) => {
+                        error!(err=?e, "Unable to process audit event to json.");
+                        warn!(?audit_record, json=false);
+                    }
+                }
+
+                if let Err(e) = audit_store.insert(&audit_record) {
+                    error!(err=?e, "Unable to store audit event in the hot tier.");
+                }
+            }
+        }
+    }
+    info!("Stopped {}", TaskName::AuditdActor);
+}
+
 pub async fn create_server_core(
     config: Configuration,
     config_test: bool,
 ) -> Result<CoreHandle, ()> {
     // Until this point, we probably want to write to the log macro fns.
-    let (broadcast_tx, mut broadcast_rx) = broadcast::channel(4);
+    let (broadcast_tx, broadcast_rx) = broadcast::channel(4);
 
     if config.integration_test_config.is_some() {
         warn!("RUNNING IN INTEGRATION TEST MODE.");
@@ -805,6 +1483,26 @@ pub async fn create_server_core(
         return Err(());
     }
 
+    if let Err(err) = config.validate_role() {
+        error!("Configuration is invalid - {}", err);
+        return Err(());
+    }
+
+    if let Err(err) = config.validate_changelog() {
+        error!("Configuration is invalid - {}", err);
+        return Err(());
+    }
+
+    if let Err(err) = config.validate_hooks() {
+        error!("Configuration is invalid - {}", err);
+        return Err(());
+    }
+
+    if let Err(err) = config.validate_tenants() {
+        error!("Configuration is invalid - {}", err);
+        return Err(());
+    }
+
     info!(
         "Starting kanidm with {}configuration: {}",
         if config_test { "TEST " } else { "" },
@@ -821,13 +1519,15 @@ pub async fn create_server_core(
     let status_ref = StatusActor::start();
 
     // Setup TLS (if any)
-    let maybe_tls_acceptor = match crypto::setup_tls(&config.tls_config) {
-        Ok(tls_acc) => tls_acc,
-        Err(err) => {
-            error!(?err, "Failed to configure TLS acceptor");
-            return Err(());
-        }
-    };
+    let tenant_tls_configs = config.tenant_tls_configs();
+    let maybe_tls_acceptor =
+        match crypto::setup_tls_with_tenants(&config.tls_config, &tenant_tls_configs) {
+            Ok(tls_acc) => tls_acc,
+            Err(err) => {
+                error!(?err, "Failed to configure TLS acceptor");
+                return Err(());
+            }
+        };
 
     let schema = match Schema::new() {
         Ok(s) => s,
@@ -846,14 +1546,13 @@ pub async fn create_server_core(
         }
     };
     // Start the IDM server.
-    let (_qs, idms, mut idms_delayed, mut idms_audit) =
-        match setup_qs_idms(be, schema, &config).await {
-            Ok(t) => t,
-            Err(e) => {
-                error!("Unable to setup query server or idm server -> {:?}", e);
-                return Err(());
-            }
-        };
+    let (qs, idms, idms_delayed, idms_audit) = match setup_qs_idms(be, schema, &config).await {
+        Ok(t) => t,
+        Err(e) => {
+            error!("Unable to setup query server or idm server -> {:?}", e);
+            return Err(());
+        }
+    };
 
     // Extract any configuration from the IDMS that we may need.
     // For now we just do this per run, but we need to extract this from the db later.
@@ -945,64 +1644,227 @@ pub async fn create_server_core(
         }
     };
 
-    // Arc the idms and ldap
+    let audit = match AuditStore::new(&config.audit) {
+        Ok(a) => a,
+        Err(e) => {
+            error!("Unable to open audit store -> {:?}", e);
+            return Err(());
+        }
+    };
+
+    if config.audit.activity {
+        idms.audit_activity_enable();
+    }
+
+    if config.break_glass_key.is_some() || config.break_glass_codes.is_some() {
+        let public_key_pem = match config
+            .break_glass_key
+            .as_ref()
+            .map(std::fs::read)
+            .transpose()
+        {
+            Ok(pem) => pem,
+            Err(e) => {
+                error!(?e, path = ?config.break_glass_key, "Unable to read break glass key");
+                return Err(());
+            }
+        };
+
+        let break_glass = match idms.break_glass_enable(
+            public_key_pem.as_deref(),
+            config.break_glass_codes.as_deref().map(Path::new),
+        ) {
+            Ok(break_glass) => break_glass,
+            Err(e) => {
+                error!(?e, "Unable to enable break glass authentication");
+                return Err(());
+            }
+        };
+
+        if public_key_pem.is_some() {
+            warn!("Break glass authentication of the admin account is enabled");
+        }
+        for (account, unused) in break_glass.emergency_accounts() {
+            if unused == 0 {
+                warn!(%account, "Emergency account has no unused recovery codes");
+            } else {
+                warn!(%account, %unused, "Break glass authentication of emergency account is enabled");
+            }
+        }
+    }
+
+    if let Some(password_breach_filter) = &config.password_breach_filter {
+        let data = match std::fs::read(password_breach_filter) {
+            Ok(data) => data,
+            Err(e) => {
+                error!(?e, path = %password_breach_filter, "Unable to read password breach filter");
+                return Err(());
+            }
+        };
+
+        let filter = match PasswordBreachFilter::from_bytes(&data) {
+            Ok(filter) => filter,
+            Err(e) => {
+                error!(?e, path = %password_breach_filter, "Invalid password breach filter");
+                return Err(());
+            }
+        };
+
+        if let Err(e) = idms.password_breach_enable(filter) {
+            error!(?e, "Unable to enable password breach checking");
+            return Err(());
+        }
+        info!("Loaded breached password dataset");
+    }
+
+    if let Some(geoip) = &config.geoip {
+        let geoip = match load_geoip(geoip) {
+            Ok(geoip) => geoip,
+            Err(err) => {
+                error!(%err, "Unable to load GeoIP database");
+                return Err(());
+            }
+        };
+
+        if let Err(e) = idms.geoip_enable(geoip) {
+            error!(?e, "Unable to enable GeoIP lookups");
+            return Err(());
+        }
+        info!("Loaded GeoIP database");
+    }
+
+    let mailer = match config
+        .smtp
+        .as_ref()
+        .map(|smtp| Mailer::new(smtp, &config.origin))
+        .transpose()
+    {
+        Ok(m) => m.map(Arc::new),
+        Err(e) => {
+            error!("Unable to configure smtp relay -> {}", e);
+            return Err(());
+        }
+    };
+
+    let notifications = match (&config.notifications, &mailer) {
+        (Some(notifications), Some(mailer)) => match idms.notifications_subscribe() {
+            Ok(notify_rx) => Some((notifications.clone(), mailer.clone(), notify_rx)),
+            Err(e) => {
+                error!("Unable to subscribe to security notifications -> {:?}", e);
+                return Err(());
+            }
+        },
+        (Some(_), None) => {
+            error!("Security notifications require smtp to be configured");
+            return Err(());
+        }
+        (None, _) => None,
+    };
+
+    let reports = match &mailer {
+        _ if config.reports.is_empty() => None,
+        Some(mailer) => Some(mailer.clone()),
+        None => {
+            error!("Scheduled reports require smtp to be configured");
+            return Err(());
+        }
+    };
+
+    // Email links can only be offered when they can be delivered.
+    let email_links = match &mailer {
+        Some(mailer) => match idms.email_link_subscribe() {
+            Ok(email_link_rx) => Some((mailer.clone(), email_link_rx)),
+            Err(e) => {
+                error!("Unable to subscribe to email links -> {:?}", e);
+                return Err(());
+            }
+        },
+        None => None,
+    };
+
+    // Arc the idms, ldap and audit store
     let idms_arc = Arc::new(idms);
     let ldap_arc = Arc::new(ldap);
+    let audit_arc = Arc::new(audit);
 
     // Pass it to the actor for threading.
     // Start the read query server with the given be path: future config
-    let server_read_ref = QueryServerReadV1::start_static(idms_arc.clone(), ldap_arc.clone());
+    let server_read_ref =
+        QueryServerReadV1::start_static(idms_arc.clone(), ldap_arc.clone(), audit_arc.clone());
 
     // Create the server async write entry point.
-    let server_write_ref = QueryServerWriteV1::start_static(idms_arc.clone());
+    let server_write_ref = QueryServerWriteV1::start_static(idms_arc.clone(), mailer);
 
-    let delayed_handle = task::spawn(async move {
-        let mut buffer = Vec::with_capacity(DELAYED_ACTION_BATCH_SIZE);
-        loop {
-            tokio::select! {
-                added = idms_delayed.recv_many(&mut buffer) => {
-                    if added == 0 {
-                        // Channel has closed, stop the task.
-                        break
-                    }
-                    server_write_ref.handle_delayedaction(&mut buffer).await;
-                }
-                Ok(action) = broadcast_rx.recv() => {
-                    match action {
-                        CoreAction::Shutdown => break,
-                    }
-                }
-            }
+    // Assert our key material and clock are sane before we start to serve requests.
+    let self_test_result = server_read_ref
+        .handle_self_test(config.self_test.max_clock_skew(), Uuid::new_v4())
+        .await
+        .map(|report| {
+            admin::log_self_test_report(&report);
+            report.status()
+        });
+
+    match (self_test_result, config.self_test.severity) {
+        (Ok(SelfTestStatus::Fail), SelfTestSeverity::Refuse) => {
+            error!("Startup self test failed, refusing to start");
+            return Err(());
         }
-        info!("Stopped {}", TaskName::DelayedActionActor);
-    });
+        (Err(err), SelfTestSeverity::Refuse) => {
+            error!(
+                ?err,
+                "Unable to perform startup self test, refusing to start"
+            );
+            return Err(());
+        }
+        (Ok(SelfTestStatus::Fail), SelfTestSeverity::Warn) => {
+            warn!("Startup self test failed, continuing as self_test.severity is warn");
+        }
+        (Err(err), SelfTestSeverity::Warn) => {
+            warn!(
+                ?err,
+                "Unable to perform startup self test, continuing as self_test.severity is warn"
+            );
+        }
+        (Ok(_), _) => {}
+    }
 
-    let mut broadcast_rx = broadcast_tx.subscribe();
+    if config.entry_cache_warmup {
+        match server_read_ref
+            .handle_warm_entry_cache(Uuid::new_v4())
+            .await
+        {
+            Ok(entries) => info!(entries, "Warmed the entry cache"),
+            Err(err) => warn!(?err, "Unable to warm the entry cache"),
+        }
+    }
 
-    let auditd_handle = task::spawn(async move {
-        loop {
-            tokio::select! {
-                Ok(action) = broadcast_rx.recv() => {
-                    match action {
-                        CoreAction::Shutdown => break,
-                    }
-                }
-                audit_event = idms_audit.audit_rx().recv() => {
-                    match serde_json::to_string(&audit_event) {
-                        Ok(audit_event) => {
-                            warn!(%audit_event);
-                        }
-                        Err(e) => {
-                            error!(err=?e, "Unable to process audit event to json.");
-                            warn!(?audit_event, json=false);
-                        }
-                    }
+    // A read only replica never supplies changes, so any changes it made locally would be lost.
+    let read_only_replica = config.role == ServerRole::ReadOnlyReplica;
 
-                }
-            }
-        }
-        info!("Stopped {}", TaskName::AuditdActor);
-    });
+    let delayed_handle = task::spawn(delayed_action_loop(
+        idms_delayed,
+        server_write_ref,
+        read_only_replica,
+        broadcast_rx,
+    ));
+
+    // Entry changes are sent to webhooks and hooks from the activity events.
+    let (webhook_tx, webhook_rx) = mpsc::unbounded_channel();
+
+    let (hook_tx, hook_rx) = if config.hooks.is_empty() {
+        (None, None)
+    } else {
+        let (hook_tx, hook_rx) = mpsc::unbounded_channel();
+        (Some(hook_tx), Some(hook_rx))
+    };
+
+    let auditd_handle = task::spawn(auditd_loop(
+        idms_audit,
+        audit_arc.clone(),
+        webhook_tx,
+        hook_tx,
+        broadcast_tx.subscribe(),
+    ));
 
     // Setup a TLS Acceptor Reload trigger.
 
@@ -1024,7 +1886,10 @@ pub async fn create_server_core(
                     }
                 }
                 _ = tls_accepter_reload_task_notify.notified() => {
-                    let tls_acceptor = match crypto::setup_tls(&tls_config) {
+                    let tls_acceptor = match crypto::setup_tls_with_tenants(
+                        &tls_config,
+                        &tenant_tls_configs,
+                    ) {
                         Ok(Some(tls_acc)) => tls_acc,
                         Ok(None) => {
                             warn!("TLS not configured, ignoring reload request.");
@@ -1052,17 +1917,28 @@ pub async fn create_server_core(
         info!("Stopped {}", TaskName::TlsAcceptorReload);
     });
 
+    // PostgreSQL reclaims the space of purged entries itself, so only SQLite is compacted.
+    let mut changelog = config.changelog.clone();
+    changelog.compact &= config.db_url.is_none();
+
     // Setup timed events associated to the write thread
-    let interval_handle = IntervalActor::start(server_write_ref, broadcast_tx.subscribe());
+    let interval_handle = IntervalActor::start(
+        server_write_ref,
+        read_only_replica,
+        &changelog,
+        broadcast_tx.subscribe(),
+    );
     // Setup timed events associated to the read thread
+    let (backup_tx, _) = broadcast::channel(1);
     let maybe_backup_handle = match &config.online_backup {
         Some(online_backup_config) => {
             if online_backup_config.enabled {
+                let plan = OnlineBackupPlan::new(online_backup_config)?;
                 let handle = IntervalActor::start_online_backup(
                     server_read_ref,
-                    online_backup_config,
-                    broadcast_tx.subscribe(),
-                )?;
+                    plan,
+                    backup_tx.subscribe(),
+                );
                 Some(handle)
             } else {
                 debug!("Backups disabled");
@@ -1075,6 +1951,83 @@ pub async fn create_server_core(
         }
     };
 
+    let maybe_notification_handle = notifications.map(|(notifications, mailer, notify_rx)| {
+        NotificationActor::start(
+            idms_arc.clone(),
+            audit_arc.clone(),
+            mailer,
+            notifications,
+            notify_rx,
+            broadcast_tx.subscribe(),
+        )
+    });
+
+    let maybe_email_link_handle = email_links.map(|(mailer, email_link_rx)| {
+        EmailLinkActor::start(
+            idms_arc.clone(),
+            mailer,
+            email_link_rx,
+            broadcast_tx.subscribe(),
+        )
+    });
+
+    let maybe_report_handle = reports
+        .map(|mailer| {
+            ReportActor::start(
+                idms_arc.clone(),
+                mailer,
+                &config.reports,
+                config
+                    .repl_config
+                    .as_ref()
+                    .and_then(|repl| repl.get_consumer_stale_threshold()),
+                broadcast_tx.subscribe(),
+            )
+        })
+        .transpose()?;
+
+    let webhook_handle = WebhookActor::start(
+        idms_arc.clone(),
+        audit_arc.clone(),
+        webhook_rx,
+        broadcast_tx.subscribe(),
+    )?;
+
+    let maybe_hook_handle = hook_rx.map(|hook_rx| {
+        HookActor::start(
+            idms_arc.clone(),
+            &config.hooks,
+            hook_rx,
+            broadcast_tx.subscribe(),
+        )
+    });
+
+    let maybe_acme_handle = match (&config.acme, &config.tls_config) {
+        (Some(acme_config), Some(tls_config)) if !config_test => Some(AcmeActor::start(
+            acme_config,
+            tls_config,
+            &config.origin,
+            idms_arc.clone(),
+            tls_acceptor_reload_notify.clone(),
+            broadcast_tx.subscribe(),
+        )?),
+        (Some(_), None) => {
+            error!("ACME requires tls_chain and tls_key to be configured");
+            return Err(());
+        }
+        _ => None,
+    };
+
+    let maybe_audit_export_handle = if !config_test {
+        Some(IntervalActor::start_audit_export(
+            audit_arc,
+            &config.audit,
+            broadcast_tx.subscribe(),
+        )?)
+    } else {
+        None
+    };
+
     // If we have been requested to init LDAP, configure it now.
     let maybe_ldap_acceptor_handle = match &config.ldapaddress {
         Some(la) => {
@@ -1116,6 +2069,24 @@ pub async fn create_server_core(
         }
     };
 
+    // Tenants are started before the https server that directs requests to them.
+    let mut tenant_servers = Vec::with_capacity(config.tenants.len());
+    let mut tenant_handles = Vec::new();
+    for tenant in &config.tenants {
+        let (tenant_server, handles) =
+            TenantServer::start(&config, tenant, config_test, &broadcast_tx).await?;
+        tenant_servers.push(tenant_server);
+        tenant_handles.extend(handles);
+    }
+
+    // Accounts of tenants can be recovered from the admin socket.
+    let tenants_rw: BTreeMap<String, &'static QueryServerWriteV1> = tenant_servers
+        .iter()
+        .map(|tenant_server| (tenant_server.config.domain.clone(), tenant_server.qe_w_ref))
+        .collect();
+
+    let trust_x_forward_for = Arc::new(AtomicBool::new(config.trust_x_forward_for));
+
     let maybe_http_acceptor_handle = if config_test {
         admin_info!("This config rocks! 🪨 ");
         None
@@ -1129,6 +2100,8 @@ pub async fn create_server_core(
             broadcast_tx.clone(),
             maybe_tls_acceptor,
             http_tls_acceptor_reload_rx,
+            trust_x_forward_for.clone(),
+            tenant_servers,
         )
         .await
         {
@@ -1146,6 +2119,9 @@ pub async fn create_server_core(
         Some(h)
     };
 
+    // Requests to reload the configuration are made from the admin socket.
+    let (config_reload_tx, config_reload_rx) = mpsc::channel(1);
+
     // If we are NOT in integration test mode, start the admin socket now
     let maybe_admin_sock_handle = if config.integration_test_config.is_none() {
         let broadcast_rx = broadcast_tx.subscribe();
@@ -1156,6 +2132,9 @@ pub async fn create_server_core(
             server_read_ref,
             broadcast_rx,
             maybe_repl_ctrl_tx,
+            config.self_test.max_clock_skew(),
+            config_reload_tx,
+            tenants_rw,
         )
         .await?;
 
@@ -1169,10 +2148,33 @@ pub async fn create_server_core(
         (TaskName::DelayedActionActor, delayed_handle),
         (TaskName::AuditdActor, auditd_handle),
         (TaskName::TlsAcceptorReload, tls_acceptor_reload_handle),
+        (TaskName::WebhookActor, webhook_handle),
     ];
 
-    if let Some(backup_handle) = maybe_backup_handle {
-        handles.push((TaskName::BackupActor, backup_handle))
+    handles.extend(tenant_handles);
+
+    if let Some(acme_handle) = maybe_acme_handle {
+        handles.push((TaskName::AcmeActor, acme_handle))
+    }
+
+    if let Some(audit_export_handle) = maybe_audit_export_handle {
+        handles.push((TaskName::AuditExportActor, audit_export_handle))
+    }
+
+    if let Some(notification_handle) = maybe_notification_handle {
+        handles.push((TaskName::NotificationActor, notification_handle))
+    }
+
+    if let Some(report_handle) = maybe_report_handle {
+        handles.push((TaskName::ReportActor, report_handle))
+    }
+
+    if let Some(hook_handle) = maybe_hook_handle {
+        handles.push((TaskName::HookActor, hook_handle))
+    }
+
+    if let Some(email_link_handle) = maybe_email_link_handle {
+        handles.push((TaskName::EmailLinkActor, email_link_handle))
     }
 
     if let Some(admin_sock_handle) = maybe_admin_sock_handle {
@@ -1194,7 +2196,85 @@ pub async fn create_server_core(
     Ok(CoreHandle {
         clean_shutdown: false,
         tls_acceptor_reload_notify,
+        config,
+        trust_x_forward_for,
+        backup_tx,
+        backup_handle: maybe_backup_handle,
+        config_reload_rx,
         tx: broadcast_tx,
         handles,
+        qs,
+        idms: idms_arc,
+        qe_r_ref: server_read_ref,
+        qe_w_ref: server_write_ref,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// This needs a PostgreSQL database to migrate into, and is skipped when
+    /// `KANIDM_TEST_POSTGRES_URL` isn't set.
+    #[tokio::test]
+    async fn test_migrate_sqlite_to_postgres_server_core() {
+        let Ok(db_url) = std::env::var("KANIDM_TEST_POSTGRES_URL") else {
+            eprintln!("KANIDM_TEST_POSTGRES_URL is not set, skipping");
+            return;
+        };
+        sketching::test_init();
+
+        let sqlite_path = std::env::temp_dir()
+            .join(format!("kanidm-migrate-{}.db", Uuid::new_v4()))
+            .to_str()
+            .expect("Invalid temporary path")
+            .to_string();
+
+        // A new server that stores its database in SQLite.
+        let mut sqlite_config = Configuration::new_for_test();
+        sqlite_config.update_db_path(&sqlite_path);
+
+        let schema = Schema::new().expect("Failed to setup schema");
+        let be = setup_backend(&sqlite_config, &schema).expect("Failed to setup SQLite");
+        let (qs, _idms, _idms_delayed, _idms_audit) =
+            setup_qs_idms(be.clone(), schema, &sqlite_config)
+                .await
+                .expect("Failed to initialise SQLite");
+        let s_uuid = be
+            .write()
+            .and_then(|mut be_txn| be_txn.get_db_s_uuid())
+            .expect("Failed to read server uuid");
+        let admin_uuid = qs
+            .read()
+            .await
+            .and_then(|mut qs_read| qs_read.name_to_uuid("admin"))
+            .expect("Failed to find admin");
+        drop(qs);
+        drop(be);
+
+        let mut pg_config = sqlite_config.clone();
+        pg_config.update_db_url(&Some(db_url), &None);
+        migrate_sqlite_server_core(&pg_config, &sqlite_path).await;
+
+        // The content and the identity of the server were copied, and the indexes rebuilt.
+        let curtime = duration_from_epoch_now();
+        let schema = Schema::new().expect("Failed to setup schema");
+        let be = setup_backend(&pg_config, &schema).expect("Failed to setup PostgreSQL");
+        assert_eq!(
+            be.write().and_then(|mut be_txn| be_txn.get_db_s_uuid()),
+            Ok(s_uuid)
+        );
+
+        let qs = QueryServer::new(be, schema, pg_config.domain.clone(), curtime)
+            .expect("Failed to setup query server");
+        assert!(qs.verify_report(curtime).await.is_empty());
+        assert_eq!(
+            qs.read()
+                .await
+                .and_then(|mut qs_read| qs_read.name_to_uuid("admin")),
+            Ok(admin_uuid)
+        );
+
+        let _ = std::fs::remove_file(&sqlite_path);
+    }
+}
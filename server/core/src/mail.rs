@@ -0,0 +1,394 @@
+//! Delivery of mail through the configured SMTP relay. This is used to send account recovery
+//! codes, email links and security notifications to the people they concern. Each mail is
+//! rendered from a plain text template, and sent alongside a html version that carries the
+//! domain branding.
+
+use askama::Template;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, Message, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Address, AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use kanidmd_lib::idm::accountrecovery::AccountRecoveryMessage;
+use kanidmd_lib::idm::credupdatesession::CredentialUpdateIntentToken;
+use kanidmd_lib::idm::emaillink::EmailLinkMessage;
+use kanidmd_lib::idm::notification::NotificationRecipient;
+use kanidmd_lib::prelude::OperationError;
+use kanidmd_lib::server::DomainInfo;
+
+use crate::config::{SmtpConfig, SmtpSecurity};
+
+/// How mail is branded. This is taken from the domain unless it is overridden in the smtp
+/// configuration.
+pub(crate) struct MailBranding {
+    name: String,
+    logo_url: String,
+    origin: String,
+    footer: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "mail/layout.txt")]
+struct MailTextLayout<'a> {
+    branding: &'a MailBranding,
+    body: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "mail/layout.html")]
+struct MailHtmlLayout<'a> {
+    branding: &'a MailBranding,
+    subject: &'a str,
+    paragraphs: Vec<&'a str>,
+}
+
+#[derive(Template)]
+#[template(path = "mail/account_recovery.txt")]
+struct AccountRecoveryMail<'a> {
+    message: &'a AccountRecoveryMessage,
+    origin: &'a str,
+    expiry: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/credential_reset.txt")]
+struct CredentialResetMail<'a> {
+    token: &'a CredentialUpdateIntentToken,
+    origin: &'a str,
+    expiry: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/email_link.txt")]
+struct EmailLinkMail<'a> {
+    message: &'a EmailLinkMessage,
+    origin: &'a str,
+    expiry: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/new_device_session.txt")]
+pub(crate) struct NewDeviceSessionMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub origin: &'a str,
+    pub source: String,
+    pub time: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/unusual_sign_in.txt")]
+pub(crate) struct UnusualSignInMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub origin: &'a str,
+    pub source: String,
+    pub anomaly: String,
+    pub time: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/credential_changed.txt")]
+pub(crate) struct CredentialChangedMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub time: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/passkey_enrolled.txt")]
+pub(crate) struct PasskeyEnrolledMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub origin: &'a str,
+    pub label: &'a str,
+    pub time: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/account_locked.txt")]
+pub(crate) struct AccountLockedMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub until: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/token_expiring.txt")]
+pub(crate) struct TokenExpiringMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub label: &'a str,
+    pub expiry: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/sshkey_expiring.txt")]
+pub(crate) struct SshKeyExpiringMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub label: &'a str,
+    pub expiry: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/contractor_expiring.txt")]
+pub(crate) struct ContractorExpiringMail<'a> {
+    pub sponsor: &'a NotificationRecipient,
+    pub displayname: &'a str,
+    pub spn: &'a str,
+    pub expiry: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/group_membership_requested.txt")]
+pub(crate) struct GroupMembershipRequestedMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub origin: &'a str,
+    pub requester: &'a str,
+    pub group: &'a str,
+    pub justification: Option<&'a str>,
+    pub time: String,
+}
+
+/// Several notifications for one account that are sent together, so that a burst of events
+/// doesn't flood the recipient with mail.
+#[derive(Template)]
+#[template(path = "mail/security_digest.txt")]
+pub(crate) struct SecurityDigestMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub origin: &'a str,
+    pub events: Vec<String>,
+}
+
+/// The mail that carries a scheduled report. The report itself is attached.
+#[derive(Template)]
+#[template(path = "mail/report.txt")]
+pub(crate) struct ReportMail<'a> {
+    pub recipient: &'a NotificationRecipient,
+    pub name: &'a str,
+    pub title: &'a str,
+    pub time: String,
+    pub summary: &'a [String],
+}
+
+/// A file that is attached to a mail.
+pub(crate) struct MailAttachment {
+    pub filename: String,
+    pub content_type: &'static str,
+    pub content: Vec<u8>,
+}
+
+/// Format a time for display in a mail.
+pub(crate) fn mail_time(time: OffsetDateTime) -> String {
+    time.format(&Rfc3339).unwrap_or_else(|_| time.to_string())
+}
+
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    origin: String,
+    brand_name: Option<String>,
+    footer: Option<String>,
+}
+
+impl Mailer {
+    pub fn new(config: &SmtpConfig, origin: &str) -> Result<Self, String> {
+        let from: Mailbox = config
+            .from
+            .parse()
+            .map_err(|err| format!("Invalid smtp from address {} - {:?}", config.from, err))?;
+
+        let mut builder = match config.security {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.relay),
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.relay)
+            }
+            SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &config.relay,
+            )),
+        }
+        .map_err(|err| format!("Invalid smtp relay {} - {:?}", config.relay, err))?;
+
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Mailer {
+            transport: builder.build(),
+            from,
+            origin: origin.to_string(),
+            brand_name: config.brand_name.clone(),
+            footer: config.footer.clone(),
+        })
+    }
+
+    pub(crate) fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    pub(crate) fn branding(&self, domain_info: &DomainInfo) -> MailBranding {
+        let logo_url = if domain_info.image().is_some() {
+            format!("{}/ui/images/domain", self.origin)
+        } else {
+            format!("{}/pkg/img/logo-square.svg", self.origin)
+        };
+
+        MailBranding {
+            name: self
+                .brand_name
+                .clone()
+                .unwrap_or_else(|| domain_info.display_name().to_string()),
+            logo_url,
+            origin: self.origin.clone(),
+            footer: self.footer.clone(),
+        }
+    }
+
+    /// Render and send a mail. The body template provides the plain text content, which is
+    /// wrapped in the branded layouts.
+    pub(crate) async fn send<T: Template>(
+        &self,
+        branding: &MailBranding,
+        to_name: &str,
+        to_mail: &str,
+        subject: &str,
+        body: &T,
+    ) -> Result<(), OperationError> {
+        self.send_with_attachment(branding, to_name, to_mail, subject, body, None)
+            .await
+    }
+
+    /// Render and send a mail as [Mailer::send], with a file attached.
+    pub(crate) async fn send_with_attachment<T: Template>(
+        &self,
+        branding: &MailBranding,
+        to_name: &str,
+        to_mail: &str,
+        subject: &str,
+        body: &T,
+        attachment: Option<&MailAttachment>,
+    ) -> Result<(), OperationError> {
+        let to = to_mail
+            .parse::<Address>()
+            .map(|address| Mailbox::new(Some(to_name.to_string()), address))
+            .map_err(|err| {
+                error!(?err, "Invalid mail address");
+                OperationError::KG005MailDeliveryFailed
+            })?;
+
+        let render_err = |err: askama::Error| {
+            error!(?err, "Unable to render mail template");
+            OperationError::KG005MailDeliveryFailed
+        };
+
+        let body = body.render().map_err(render_err)?;
+
+        let text = MailTextLayout {
+            branding,
+            body: &body,
+        }
+        .render()
+        .map_err(render_err)?;
+
+        let html = MailHtmlLayout {
+            branding,
+            subject,
+            paragraphs: body.split("\n\n").map(str::trim).collect(),
+        }
+        .render()
+        .map_err(render_err)?;
+
+        let mut content = MultiPart::alternative_plain_html(text, html);
+
+        if let Some(attachment) = attachment {
+            let content_type = ContentType::parse(attachment.content_type).map_err(|err| {
+                error!(?err, "Invalid mail attachment content type");
+                OperationError::KG005MailDeliveryFailed
+            })?;
+            content = MultiPart::mixed().multipart(content).singlepart(
+                Attachment::new(attachment.filename.clone())
+                    .body(attachment.content.clone(), content_type),
+            );
+        }
+
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(format!("{} {}", branding.name, subject))
+            .multipart(content)
+            .map_err(|err| {
+                error!(?err, "Unable to build mail");
+                OperationError::KG005MailDeliveryFailed
+            })?;
+
+        self.transport.send(email).await.map(|_| ()).map_err(|err| {
+            error!(?err, "Unable to send mail");
+            OperationError::KG005MailDeliveryFailed
+        })
+    }
+
+    pub(crate) async fn send_account_recovery(
+        &self,
+        branding: &MailBranding,
+        message: &AccountRecoveryMessage,
+    ) -> Result<(), OperationError> {
+        let body = AccountRecoveryMail {
+            message,
+            origin: &self.origin,
+            expiry: mail_time(message.expiry),
+        };
+
+        self.send(
+            branding,
+            &message.displayname,
+            &message.mail,
+            "account recovery code",
+            &body,
+        )
+        .await
+    }
+    /// Send a credential reset token directly to the person it was issued for.
+    pub(crate) async fn send_credential_reset(
+        &self,
+        branding: &MailBranding,
+        token: &CredentialUpdateIntentToken,
+        to_mail: &str,
+    ) -> Result<(), OperationError> {
+        let body = CredentialResetMail {
+            token,
+            origin: &self.origin,
+            expiry: mail_time(token.expiry_time),
+        };
+
+        self.send(
+            branding,
+            &token.displayname,
+            to_mail,
+            "credential reset",
+            &body,
+        )
+        .await
+    }
+
+    /// Send an email link to the person who is authenticating.
+    pub(crate) async fn send_email_link(
+        &self,
+        branding: &MailBranding,
+        message: &EmailLinkMessage,
+    ) -> Result<(), OperationError> {
+        let body = EmailLinkMail {
+            message,
+            origin: &self.origin,
+            expiry: mail_time(message.expiry),
+        };
+
+        self.send(
+            branding,
+            &message.displayname,
+            &message.mail,
+            "login link",
+            &body,
+        )
+        .await
+    }
+}
@@ -0,0 +1,597 @@
+//! Delivery of security notifications to account owners by mail. Notifications are produced by
+//! the idm server as events occur, and the expiry of api tokens, ssh public keys and contractor
+//! accounts is checked on an interval. Notifications for an account are held briefly so that
+//! a burst of events is delivered as a single mail.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use kanidmd_lib::idm::geoip::GeoLocation;
+use kanidmd_lib::idm::notification::{NotificationRecipient, SecurityNotification};
+use kanidmd_lib::idm::server::IdmServer;
+use kanidmd_lib::prelude::{OperationError, Uuid};
+use kanidmd_lib::server::identity::Source;
+
+use crate::audit::AuditStore;
+use crate::config::{NotificationConfig, NotificationEvent};
+use crate::mail::{
+    mail_time, AccountLockedMail, ContractorExpiringMail, CredentialChangedMail,
+    GroupMembershipRequestedMail, Mailer, NewDeviceSessionMail, PasskeyEnrolledMail,
+    SecurityDigestMail, SshKeyExpiringMail, TokenExpiringMail, UnusualSignInMail,
+};
+use crate::CoreAction;
+
+/// How often api tokens, ssh public keys and contractors are checked for upcoming expiry.
+const TOKEN_EXPIRY_CHECK_FREQUENCY: u64 = 3600;
+
+/// The address of a session, followed by where it is located if that is known.
+fn describe_source(address: String, location: Option<&GeoLocation>) -> String {
+    match location.map(ToString::to_string) {
+        Some(location) if !location.is_empty() => format!("{} ({})", address, location),
+        _ => address,
+    }
+}
+
+/// A notification that has been accepted for delivery to an account.
+struct PendingNotification {
+    notification: SecurityNotification,
+    /// Where a new session came from, including where it is located if that is known.
+    source: Option<String>,
+}
+
+impl PendingNotification {
+    /// A single line description of the notification, used when several are sent together.
+    fn summary(&self) -> String {
+        match &self.notification {
+            SecurityNotification::SessionCreated { time, .. } => format!(
+                "A new session was created from {} at {}.",
+                self.source.as_deref().unwrap_or_default(),
+                mail_time(*time)
+            ),
+            SecurityNotification::UnusualSignIn { anomaly, time, .. } => format!(
+                "An unusual sign in from {} at {}, as it was {}.",
+                self.source.as_deref().unwrap_or_default(),
+                mail_time(*time),
+                anomaly
+            ),
+            SecurityNotification::CredentialUpdated { time, .. } => {
+                format!("The credentials were changed at {}.", mail_time(*time))
+            }
+            SecurityNotification::PasskeyEnrolled { label, time, .. } => format!(
+                "A new passkey named \"{}\" was enrolled at {}.",
+                label,
+                mail_time(*time)
+            ),
+            SecurityNotification::AccountLocked { until, .. } => format!(
+                "A credential was locked after repeated failed authentications until {}.",
+                mail_time(*until)
+            ),
+            SecurityNotification::GroupMembershipRequested {
+                requester,
+                group,
+                time,
+                ..
+            } => format!(
+                "{} requested to join the group {} at {}.",
+                requester,
+                group,
+                mail_time(*time)
+            ),
+        }
+    }
+}
+
+/// The notifications for an account that are waiting to be sent.
+struct PendingBatch {
+    recipient: NotificationRecipient,
+    notifications: Vec<PendingNotification>,
+}
+
+pub(crate) struct NotificationActor {
+    idms: Arc<IdmServer>,
+    audit: Arc<AuditStore>,
+    mailer: Arc<Mailer>,
+    config: NotificationConfig,
+    pending: BTreeMap<Uuid, PendingBatch>,
+}
+
+impl NotificationActor {
+    pub fn start(
+        idms: Arc<IdmServer>,
+        audit: Arc<AuditStore>,
+        mailer: Arc<Mailer>,
+        config: NotificationConfig,
+        mut notify_rx: UnboundedReceiver<SecurityNotification>,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> tokio::task::JoinHandle<()> {
+        let mut actor = NotificationActor {
+            idms,
+            audit,
+            mailer,
+            config,
+            pending: BTreeMap::new(),
+        };
+
+        tokio::spawn(async move {
+            let mut inter = interval(Duration::from_secs(TOKEN_EXPIRY_CHECK_FREQUENCY));
+            inter.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            // Each check covers the time since the previous one, so that every token is only
+            // warned about once. Tokens that enter the warning window while the server is
+            // stopped are not warned about.
+            let mut last_checked = time::OffsetDateTime::now_utc();
+
+            // When batching is disabled nothing is ever pending, so the flush is a no-op.
+            let mut flush = interval(
+                actor
+                    .config
+                    .batch_delay()
+                    .unwrap_or(Duration::from_secs(TOKEN_EXPIRY_CHECK_FREQUENCY)),
+            );
+            flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                        }
+                    }
+                    notification = notify_rx.recv() => {
+                        let Some(notification) = notification else {
+                            // Channel has closed, stop the task.
+                            break
+                        };
+                        if let Err(err) = actor.handle_notification(notification).await {
+                            error!(?err, "Unable to deliver security notification");
+                        }
+                    }
+                    _ = flush.tick() => {
+                        actor.flush_pending().await;
+                    }
+                    _ = inter.tick() => {
+                        let now = time::OffsetDateTime::now_utc();
+                        if let Err(err) = actor.handle_token_expiry(last_checked, now).await {
+                            error!(?err, "Unable to deliver api token expiry notifications");
+                        }
+                        if let Err(err) = actor.handle_sshkey_expiry(last_checked, now).await {
+                            error!(?err, "Unable to deliver ssh public key expiry notifications");
+                        }
+                        if let Err(err) = actor.handle_contractor_expiry(last_checked, now).await {
+                            error!(?err, "Unable to deliver contractor expiry notifications");
+                        }
+                        last_checked = now;
+                    }
+                }
+            }
+
+            // Don't lose anything that was waiting for its batch to be sent.
+            actor.flush_pending().await;
+
+            info!("Stopped {}", super::TaskName::NotificationActor);
+        })
+    }
+
+    async fn recipient(&self, uuid: Uuid) -> Result<Option<NotificationRecipient>, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        idms_prox_read.notification_recipient(uuid)
+    }
+
+    /// If the recipient should be sent this event. Account owners may opt out of any event
+    /// that the configuration doesn't require them to receive.
+    fn permits(&self, recipient: &NotificationRecipient, event: NotificationEvent) -> bool {
+        self.config.is_enabled(event)
+            && (self.config.is_required(event) || !recipient.has_opted_out(&event.to_string()))
+    }
+
+    async fn handle_notification(
+        &mut self,
+        notification: SecurityNotification,
+    ) -> Result<(), OperationError> {
+        let event = match &notification {
+            SecurityNotification::SessionCreated { .. } => NotificationEvent::NewDeviceSession,
+            SecurityNotification::UnusualSignIn { .. } => NotificationEvent::UnusualSignIn,
+            SecurityNotification::CredentialUpdated { .. } => NotificationEvent::CredentialChanged,
+            SecurityNotification::PasskeyEnrolled { .. } => NotificationEvent::PasskeyEnrolled,
+            SecurityNotification::AccountLocked { .. } => NotificationEvent::AccountLocked,
+            SecurityNotification::GroupMembershipRequested { .. } => {
+                NotificationEvent::GroupMembershipRequested
+            }
+        };
+
+        if !self.config.is_enabled(event) {
+            return Ok(());
+        }
+
+        // New sessions are only of interest if they come from somewhere unfamiliar. This is
+        // checked before the recipient so that the source is recorded for every account.
+        let source = match &notification {
+            SecurityNotification::SessionCreated {
+                uuid,
+                source,
+                location,
+                time,
+            } => {
+                let source = match source {
+                    Source::Https(ip) | Source::Ldaps(ip) => ip.to_string(),
+                    Source::Internal => return Ok(()),
+                };
+                if !self.audit.record_session_source(*uuid, &source, *time)? {
+                    return Ok(());
+                }
+                Some(describe_source(source, location.as_ref()))
+            }
+            SecurityNotification::UnusualSignIn { source, .. } => match source {
+                Source::Https(ip) | Source::Ldaps(ip) => Some(ip.to_string()),
+                Source::Internal => return Ok(()),
+            },
+            _ => None,
+        };
+
+        let Some(recipient) = self.recipient(notification.uuid()).await? else {
+            debug!(uuid = ?notification.uuid(), "Account has no mail address, not notifying");
+            return Ok(());
+        };
+
+        if !self.permits(&recipient, event) {
+            debug!(uuid = ?recipient.uuid, %event, "Account has opted out, not notifying");
+            return Ok(());
+        }
+
+        let pending = PendingNotification {
+            notification,
+            source,
+        };
+
+        if self.config.batch_delay().is_none() {
+            return self.send_notification(&recipient, pending).await;
+        }
+
+        // The recipient is refreshed so that the batch is sent to the current address.
+        let batch = self
+            .pending
+            .entry(recipient.uuid)
+            .or_insert_with(|| PendingBatch {
+                recipient: recipient.clone(),
+                notifications: Vec::new(),
+            });
+        batch.recipient = recipient;
+        batch.notifications.push(pending);
+
+        Ok(())
+    }
+
+    /// Send everything that is waiting. An account with a single notification is sent the
+    /// mail for that event, otherwise the notifications are combined into one digest.
+    async fn flush_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let pending = std::mem::take(&mut self.pending);
+
+        debug!(
+            count = pending.len(),
+            "Sending pending security notifications"
+        );
+
+        let branding = self.mailer.branding(&self.idms.domain_read());
+
+        for (uuid, mut batch) in pending {
+            let result = if batch.notifications.len() == 1 {
+                match batch.notifications.pop() {
+                    Some(pending) => self.send_notification(&batch.recipient, pending).await,
+                    None => Ok(()),
+                }
+            } else {
+                let body = SecurityDigestMail {
+                    recipient: &batch.recipient,
+                    origin: self.mailer.origin(),
+                    events: batch
+                        .notifications
+                        .iter()
+                        .map(PendingNotification::summary)
+                        .collect(),
+                };
+                self.mailer
+                    .send(
+                        &branding,
+                        &batch.recipient.displayname,
+                        &batch.recipient.mail,
+                        "security events",
+                        &body,
+                    )
+                    .await
+            };
+
+            if let Err(err) = result {
+                error!(?err, ?uuid, "Unable to send security notifications");
+            }
+        }
+    }
+
+    async fn send_notification(
+        &self,
+        recipient: &NotificationRecipient,
+        pending: PendingNotification,
+    ) -> Result<(), OperationError> {
+        let branding = self.mailer.branding(&self.idms.domain_read());
+
+        match pending.notification {
+            SecurityNotification::SessionCreated { time, .. } => {
+                let body = NewDeviceSessionMail {
+                    recipient,
+                    origin: self.mailer.origin(),
+                    source: pending.source.unwrap_or_default(),
+                    time: mail_time(time),
+                };
+                self.mailer
+                    .send(
+                        &branding,
+                        &recipient.displayname,
+                        &recipient.mail,
+                        "new sign in",
+                        &body,
+                    )
+                    .await
+            }
+            SecurityNotification::UnusualSignIn { anomaly, time, .. } => {
+                let body = UnusualSignInMail {
+                    recipient,
+                    origin: self.mailer.origin(),
+                    source: pending.source.unwrap_or_default(),
+                    anomaly: anomaly.to_string(),
+                    time: mail_time(time),
+                };
+                self.mailer
+                    .send(
+                        &branding,
+                        &recipient.displayname,
+                        &recipient.mail,
+                        "unusual sign in",
+                        &body,
+                    )
+                    .await
+            }
+            SecurityNotification::CredentialUpdated { time, .. } => {
+                let body = CredentialChangedMail {
+                    recipient,
+                    time: mail_time(time),
+                };
+                self.mailer
+                    .send(
+                        &branding,
+                        &recipient.displayname,
+                        &recipient.mail,
+                        "credentials changed",
+                        &body,
+                    )
+                    .await
+            }
+            SecurityNotification::PasskeyEnrolled { label, time, .. } => {
+                let body = PasskeyEnrolledMail {
+                    recipient,
+                    origin: self.mailer.origin(),
+                    label: &label,
+                    time: mail_time(time),
+                };
+                self.mailer
+                    .send(
+                        &branding,
+                        &recipient.displayname,
+                        &recipient.mail,
+                        "passkey enrolled",
+                        &body,
+                    )
+                    .await
+            }
+            SecurityNotification::AccountLocked { until, .. } => {
+                let body = AccountLockedMail {
+                    recipient,
+                    until: mail_time(until),
+                };
+                self.mailer
+                    .send(
+                        &branding,
+                        &recipient.displayname,
+                        &recipient.mail,
+                        "account locked",
+                        &body,
+                    )
+                    .await
+            }
+            SecurityNotification::GroupMembershipRequested {
+                requester,
+                group,
+                justification,
+                time,
+                ..
+            } => {
+                let body = GroupMembershipRequestedMail {
+                    recipient,
+                    origin: self.mailer.origin(),
+                    requester: &requester,
+                    group: &group,
+                    justification: justification.as_deref(),
+                    time: mail_time(time),
+                };
+                self.mailer
+                    .send(
+                        &branding,
+                        &recipient.displayname,
+                        &recipient.mail,
+                        "group membership requested",
+                        &body,
+                    )
+                    .await
+            }
+        }
+    }
+
+    async fn handle_token_expiry(
+        &self,
+        from: time::OffsetDateTime,
+        to: time::OffsetDateTime,
+    ) -> Result<(), OperationError> {
+        if !self.config.is_enabled(NotificationEvent::TokenExpiring) {
+            return Ok(());
+        }
+
+        let warning = self.config.token_expiry_warning();
+        let tokens = {
+            let mut idms_prox_read = self.idms.proxy_read().await?;
+            idms_prox_read.expiring_api_tokens(from + warning, to + warning)?
+        };
+
+        if tokens.is_empty() {
+            return Ok(());
+        }
+
+        debug!(count = tokens.len(), "Notifying of expiring api tokens");
+
+        let branding = self.mailer.branding(&self.idms.domain_read());
+
+        for token in tokens.iter() {
+            if !self.permits(&token.recipient, NotificationEvent::TokenExpiring) {
+                continue;
+            }
+
+            let body = TokenExpiringMail {
+                recipient: &token.recipient,
+                label: &token.label,
+                expiry: mail_time(token.expiry),
+            };
+
+            // A failure for one recipient shouldn't prevent the others being notified.
+            if let Err(err) = self
+                .mailer
+                .send(
+                    &branding,
+                    &token.recipient.displayname,
+                    &token.recipient.mail,
+                    "api token expiring",
+                    &body,
+                )
+                .await
+            {
+                error!(?err, uuid = ?token.recipient.uuid, "Unable to send api token expiry notification");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_sshkey_expiry(
+        &self,
+        from: time::OffsetDateTime,
+        to: time::OffsetDateTime,
+    ) -> Result<(), OperationError> {
+        if !self.config.is_enabled(NotificationEvent::SshKeyExpiring) {
+            return Ok(());
+        }
+
+        let warning = self.config.sshkey_expiry_warning();
+        let keys = {
+            let mut idms_prox_read = self.idms.proxy_read().await?;
+            idms_prox_read.expiring_ssh_keys(from + warning, to + warning)?
+        };
+
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        debug!(count = keys.len(), "Notifying of expiring ssh public keys");
+
+        let branding = self.mailer.branding(&self.idms.domain_read());
+
+        for key in keys.iter() {
+            if !self.permits(&key.recipient, NotificationEvent::SshKeyExpiring) {
+                continue;
+            }
+
+            let body = SshKeyExpiringMail {
+                recipient: &key.recipient,
+                label: &key.label,
+                expiry: mail_time(key.expiry),
+            };
+
+            if let Err(err) = self
+                .mailer
+                .send(
+                    &branding,
+                    &key.recipient.displayname,
+                    &key.recipient.mail,
+                    "ssh public key expiring",
+                    &body,
+                )
+                .await
+            {
+                error!(?err, uuid = ?key.recipient.uuid, "Unable to send ssh public key expiry notification");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_contractor_expiry(
+        &self,
+        from: time::OffsetDateTime,
+        to: time::OffsetDateTime,
+    ) -> Result<(), OperationError> {
+        if !self
+            .config
+            .is_enabled(NotificationEvent::ContractorExpiring)
+        {
+            return Ok(());
+        }
+
+        let warning = self.config.contractor_expiry_warning();
+        let contractors = {
+            let mut idms_prox_read = self.idms.proxy_read().await?;
+            idms_prox_read.expiring_contractors(from + warning, to + warning)?
+        };
+
+        if contractors.is_empty() {
+            return Ok(());
+        }
+
+        debug!(
+            count = contractors.len(),
+            "Notifying sponsors of expiring contractors"
+        );
+
+        let branding = self.mailer.branding(&self.idms.domain_read());
+
+        for contractor in contractors.iter() {
+            if !self.permits(&contractor.sponsor, NotificationEvent::ContractorExpiring) {
+                continue;
+            }
+
+            let body = ContractorExpiringMail {
+                sponsor: &contractor.sponsor,
+                displayname: &contractor.displayname,
+                spn: &contractor.spn,
+                expiry: mail_time(contractor.expiry),
+            };
+
+            if let Err(err) = self
+                .mailer
+                .send(
+                    &branding,
+                    &contractor.sponsor.displayname,
+                    &contractor.sponsor.mail,
+                    "contractor account expiring",
+                    &body,
+                )
+                .await
+            {
+                error!(?err, uuid = ?contractor.sponsor.uuid, "Unable to send contractor expiry notification");
+            }
+        }
+
+        Ok(())
+    }
+}
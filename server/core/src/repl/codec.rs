@@ -1,20 +1,27 @@
 use bytes::{Buf, BufMut, BytesMut};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::io;
+use std::time::Duration;
 use tokio_util::codec::{Decoder, Encoder};
 
 use kanidmd_lib::repl::proto::{ReplIncrementalContext, ReplRefreshContext, ReplRuvRange};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ConsumerRequest {
-    Ping,
+    /// The current time of the consumer, used to detect clock skew between the partners.
+    Ping {
+        consumer_time: Duration,
+    },
     Incremental(ReplRuvRange),
     Refresh,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum SupplierResponse {
-    Pong,
+    /// The current time of the supplier, used to detect clock skew between the partners.
+    Pong {
+        supplier_time: Duration,
+    },
     Incremental(ReplIncrementalContext),
     Refresh(ReplRefreshContext),
 }
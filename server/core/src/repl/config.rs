@@ -1,16 +1,17 @@
 use kanidm_lib_crypto::prelude::X509;
-use kanidm_lib_crypto::serialise::x509b64;
+use kanidm_lib_crypto::serialise::{x509b64, x509b64_option};
 use kanidm_proto::constants::{
     AUTH_TOKEN_GRACE_WINDOW, DEFAULT_REPLICATION_ADDRESS, DEFAULT_REPLICATION_ORIGIN,
+    DEFAULT_REPL_CLOCK_SKEW_TOLERANCE, DEFAULT_REPL_CLOCK_SKEW_WARNING,
     DEFAULT_REPL_TASK_POLL_INTERVAL,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::net::SocketAddr;
 use std::str::FromStr;
 use url::Url;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum RepNodeConfig {
     #[serde(rename = "allow-pull")]
@@ -18,17 +19,21 @@ pub enum RepNodeConfig {
         #[serde(with = "x509b64")]
         consumer_cert: X509,
     },
+    /// Pull from a supplier. If no certificate is pinned, the supplier must present an
+    /// identity issued by the replication trust anchor of our domain.
     #[serde(rename = "pull")]
     Pull {
-        #[serde(with = "x509b64")]
-        supplier_cert: X509,
+        #[serde(default, with = "x509b64_option")]
+        supplier_cert: Option<X509>,
         #[serde(default)]
         automatic_refresh: bool,
     },
+    /// Pull from a partner and allow it to pull from us. If no certificate is pinned, the
+    /// partner must present an identity issued by the replication trust anchor of our domain.
     #[serde(rename = "mutual-pull")]
     MutualPull {
-        #[serde(with = "x509b64")]
-        partner_cert: X509,
+        #[serde(default, with = "x509b64_option")]
+        partner_cert: Option<X509>,
         #[serde(default)]
         automatic_refresh: bool,
     },
@@ -40,7 +45,7 @@ pub enum RepNodeConfig {
     */
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ReplicationConfiguration {
     /// Defaults to [kanidm_proto::constants::DEFAULT_REPLICATION_ORIGIN]
     pub origin: Url,
@@ -50,6 +55,13 @@ pub struct ReplicationConfiguration {
     /// [kanidm_proto::constants::DEFAULT_REPL_TASK_POLL_INTERVAL] but may
     /// not exceed [kanidm_proto::constants::AUTH_TOKEN_GRACE_WINDOW].
     pub task_poll_interval: Option<u64>,
+    /// Number of seconds of clock skew with a replication partner before a warning is
+    /// raised. Defaults to [kanidm_proto::constants::DEFAULT_REPL_CLOCK_SKEW_WARNING].
+    pub clock_skew_warning: Option<u64>,
+    /// Number of seconds of clock skew with a replication partner before replication
+    /// with that partner is refused. Defaults to
+    /// [kanidm_proto::constants::DEFAULT_REPL_CLOCK_SKEW_TOLERANCE].
+    pub clock_skew_tolerance: Option<u64>,
 
     #[serde(flatten)]
     pub manual: BTreeMap<Url, RepNodeConfig>,
@@ -69,6 +81,8 @@ impl Default for ReplicationConfiguration {
             origin,
             bindaddress,
             task_poll_interval: None,
+            clock_skew_warning: None,
+            clock_skew_tolerance: None,
             manual: BTreeMap::new(),
         }
     }
@@ -89,4 +103,38 @@ impl ReplicationConfiguration {
             config_poll
         }
     }
+
+    /// When this server pulls from a supplier, how long it may go without a successful
+    /// replication before it is considered degraded. Beyond the grace window, sessions that
+    /// were issued by other servers may not yet be known here.
+    pub(crate) fn get_consumer_stale_threshold(&self) -> Option<core::time::Duration> {
+        self.manual
+            .values()
+            .any(|node| {
+                matches!(
+                    node,
+                    RepNodeConfig::Pull { .. } | RepNodeConfig::MutualPull { .. }
+                )
+            })
+            .then_some(AUTH_TOKEN_GRACE_WINDOW)
+    }
+
+    /// Get the clock skew warning threshold, or the default if not set.
+    pub(crate) fn get_clock_skew_warning(&self) -> core::time::Duration {
+        core::time::Duration::from_secs(
+            self.clock_skew_warning
+                .unwrap_or(DEFAULT_REPL_CLOCK_SKEW_WARNING),
+        )
+    }
+
+    /// Get the clock skew tolerance, or the default if not set. This is never less
+    /// than the warning threshold.
+    pub(crate) fn get_clock_skew_tolerance(&self) -> core::time::Duration {
+        let tolerance = core::time::Duration::from_secs(
+            self.clock_skew_tolerance
+                .unwrap_or(DEFAULT_REPL_CLOCK_SKEW_TOLERANCE),
+        );
+
+        tolerance.max(self.get_clock_skew_warning())
+    }
 }
@@ -1,13 +1,14 @@
 use openssl::{
     pkey::{PKey, Private},
     ssl::{Ssl, SslAcceptor, SslConnector, SslMethod, SslVerifyMode},
-    x509::{store::X509StoreBuilder, X509},
+    x509::{store::X509StoreBuilder, verify::X509VerifyFlags, X509},
 };
 use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
+use time::OffsetDateTime;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
@@ -26,8 +27,10 @@ use uuid::Uuid;
 use futures_util::sink::SinkExt;
 use futures_util::stream::StreamExt;
 
+use kanidmd_lib::idm::audit::AuditEvent;
 use kanidmd_lib::prelude::duration_from_epoch_now;
 use kanidmd_lib::prelude::IdmServer;
+use kanidmd_lib::prelude::OperationError;
 use kanidmd_lib::repl::proto::ConsumerState;
 use kanidmd_lib::server::QueryServerTransaction;
 
@@ -39,6 +42,10 @@ use self::codec::{ConsumerRequest, SupplierResponse};
 mod codec;
 pub(crate) mod config;
 
+/// How often to check if our replication identity or the trust anchors of the domain have
+/// changed, such as when the identity is renewed or an anchor is revoked.
+const REPL_IDENTITY_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
 pub(crate) enum ReplCtrl {
     GetCertificate {
         respond: oneshot::Sender<X509>,
@@ -159,6 +166,108 @@ async fn repl_consumer_connect_supplier(
     None
 }
 
+/// The signed difference between a remote and local time in milliseconds. Positive values
+/// indicate the remote clock is ahead of ours.
+fn clock_skew_ms(remote: Duration, local: Duration) -> i64 {
+    let skew = remote.as_millis() as i128 - local.as_millis() as i128;
+    skew.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// Report the clock skew with a replication partner, raising an alert if it exceeds the
+/// warning threshold. Returns true if the skew exceeds the tolerance.
+fn repl_report_clock_skew(
+    idms: &IdmServer,
+    partner: &str,
+    skew_ms: i64,
+    clock_skew_warning: Duration,
+    clock_skew_tolerance: Duration,
+) -> bool {
+    let skew = Duration::from_millis(skew_ms.unsigned_abs());
+
+    if skew < clock_skew_warning {
+        debug!(%partner, %skew_ms, "replication partner clock skew");
+        return false;
+    }
+
+    let tolerance_exceeded = skew > clock_skew_tolerance;
+    if tolerance_exceeded {
+        error!(
+            %partner,
+            %skew_ms,
+            tolerance_secs = clock_skew_tolerance.as_secs(),
+            "Clock skew with replication partner exceeds the tolerance. Replication with this partner will not proceed until the clocks of both servers are corrected."
+        );
+    } else {
+        warn!(
+            %partner,
+            %skew_ms,
+            warning_secs = clock_skew_warning.as_secs(),
+            "Clock skew with replication partner exceeds the warning threshold. You should check the clocks of both servers."
+        );
+    }
+
+    idms.submit_audit_event(AuditEvent::ReplicationClockSkew {
+        partner: partner.to_string(),
+        skew_ms,
+        tolerance_exceeded,
+        time: OffsetDateTime::now_utc(),
+    });
+
+    tolerance_exceeded
+}
+
+/// Exchange the current time with the supplier to estimate the skew between our clocks. This
+/// fails if the skew exceeds the tolerance, since changes from a server with a skewed clock
+/// can override newer changes from other servers.
+async fn repl_consumer_check_clock(
+    domain: &str,
+    supplier_conn: &mut Framed<SslStream<TcpStream>, codec::ConsumerCodec>,
+    idms: &IdmServer,
+    consumer_conn_settings: &ConsumerConnSettings,
+) -> Result<(), ()> {
+    let sent = duration_from_epoch_now();
+
+    supplier_conn
+        .send(ConsumerRequest::Ping {
+            consumer_time: sent,
+        })
+        .await
+        .map_err(|err| error!(?err, "consumer encode error, unable to continue."))?;
+
+    let supplier_time = match supplier_conn.next().await {
+        Some(Ok(SupplierResponse::Pong { supplier_time })) => supplier_time,
+        Some(Ok(SupplierResponse::Incremental(_))) | Some(Ok(SupplierResponse::Refresh(_))) => {
+            error!("Supplier Response contains invalid State");
+            return Err(());
+        }
+        Some(Err(err)) => {
+            error!(?err, "consumer decode error, unable to continue.");
+            return Err(());
+        }
+        None => {
+            error!("Connection closed");
+            return Err(());
+        }
+    };
+
+    // Assume the supplier read its clock halfway through the round trip.
+    let received = duration_from_epoch_now();
+    let midpoint = sent + (received.saturating_sub(sent) / 2);
+    let skew_ms = clock_skew_ms(supplier_time, midpoint);
+
+    if repl_report_clock_skew(
+        idms,
+        domain,
+        skew_ms,
+        consumer_conn_settings.clock_skew_warning,
+        consumer_conn_settings.clock_skew_tolerance,
+    ) {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
 /// This returns the socket address that worked, so you can try that first next time
 #[instrument(level="info", skip(refresh_coord, tls_connector, idms), fields(uuid=Uuid::new_v4().to_string()))]
 async fn repl_run_consumer_refresh(
@@ -186,6 +295,8 @@ async fn repl_run_consumer_refresh(
             .await
             .ok_or(())?;
 
+    repl_consumer_check_clock(domain, &mut supplier_conn, idms, consumer_conn_settings).await?;
+
     // If we fail at any point, just RETURN because this leaves the next task to attempt, or
     // the channel drops and that tells the caller this failed.
     supplier_conn
@@ -199,7 +310,7 @@ async fn repl_run_consumer_refresh(
                 // Success - return to bypass the error message.
                 changes
             }
-            SupplierResponse::Pong | SupplierResponse::Incremental(_) => {
+            SupplierResponse::Pong { .. } | SupplierResponse::Incremental(_) => {
                 error!("Supplier Response contains invalid State");
                 return Err(());
             }
@@ -249,6 +360,10 @@ async fn repl_run_consumer(
         repl_consumer_connect_supplier(domain, sock_addrs, tls_connector, consumer_conn_settings)
             .await?;
 
+    repl_consumer_check_clock(domain, &mut supplier_conn, idms, consumer_conn_settings)
+        .await
+        .ok()?;
+
     // Perform incremental.
     let consumer_ruv_range = {
         let consumer_state = idms
@@ -281,7 +396,7 @@ async fn repl_run_consumer(
                 // Success - return to bypass the error message.
                 changes
             }
-            Ok(SupplierResponse::Pong) | Ok(SupplierResponse::Refresh(_)) => {
+            Ok(SupplierResponse::Pong { .. }) | Ok(SupplierResponse::Refresh(_)) => {
                 error!("Supplier Response contains invalid State");
                 return None;
             }
@@ -301,7 +416,10 @@ async fn repl_run_consumer(
         match idms.proxy_write(ct).await.and_then(|mut write_txn| {
             write_txn
                 .qs_write
-                .consumer_apply_changes(changes)
+                .consumer_apply_changes_with_tolerance(
+                    changes,
+                    consumer_conn_settings.clock_skew_tolerance,
+                )
                 .and_then(|cs| write_txn.commit().map(|()| cs))
         }) {
             Ok(state) => state,
@@ -339,7 +457,7 @@ async fn repl_run_consumer(
                 // Success - return to bypass the error message.
                 changes
             }
-            Ok(SupplierResponse::Pong) | Ok(SupplierResponse::Incremental(_)) => {
+            Ok(SupplierResponse::Pong { .. }) | Ok(SupplierResponse::Incremental(_)) => {
                 error!("Supplier Response contains invalid State");
                 return None;
             }
@@ -374,6 +492,8 @@ struct ConsumerConnSettings {
     max_frame_bytes: usize,
     task_poll_interval: Duration,
     replica_connect_timeout: Duration,
+    clock_skew_warning: Duration,
+    clock_skew_tolerance: Duration,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -381,7 +501,8 @@ async fn repl_task(
     origin: Url,
     client_key: PKey<Private>,
     client_cert: X509,
-    supplier_cert: X509,
+    supplier_cert: Option<X509>,
+    trust_anchors: Vec<X509>,
     consumer_conn_settings: ConsumerConnSettings,
     mut task_rx: broadcast::Receiver<ReplConsumerCtrl>,
     automatic_refresh: bool,
@@ -418,11 +539,11 @@ async fn repl_task(
         return;
     }
 
-    // Add the supplier cert.
+    // Add the supplier cert and our domain trust anchors.
     // ⚠️  note that here we need to build a new cert store. This is because
     // openssl SslConnector adds the default system cert locations with
     // the call to ::builder and we *don't* want this. We want our certstore
-    // to pin a single certificate!
+    // to only contain the pinned certificate and the anchors of our domain!
     let mut cert_store = match X509StoreBuilder::new() {
         Ok(csb) => csb,
         Err(err) => {
@@ -431,9 +552,18 @@ async fn repl_task(
         }
     };
 
-    if let Err(err) = cert_store.add_cert(supplier_cert) {
-        error!(?err, "Unable to add supplier certificate to cert store");
-        return;
+    if let Some(supplier_cert) = supplier_cert {
+        if let Err(err) = cert_store.add_cert(supplier_cert) {
+            error!(?err, "Unable to add supplier certificate to cert store");
+            return;
+        }
+    }
+
+    for trust_anchor in trust_anchors {
+        if let Err(err) = cert_store.add_cert(trust_anchor) {
+            error!(?err, "Unable to add replication trust anchor to cert store");
+            return;
+        }
     }
 
     let cert_store = cert_store.build();
@@ -446,6 +576,16 @@ async fn repl_task(
         return;
     }
 
+    // A pinned certificate may have been issued by a trust anchor, so it must be trusted
+    // by itself rather than requiring the chain to a self signed root.
+    if let Err(err) = verify_param.set_flags(X509VerifyFlags::PARTIAL_CHAIN) {
+        error!(
+            ?err,
+            "Unable to allow pinned certificates for tls peer verification"
+        );
+        return;
+    }
+
     // Assert the expected supplier certificate is correct and has a valid domain san
     ssl_builder.set_verify(SslVerifyMode::PEER);
     let tls_connector = ssl_builder.build();
@@ -547,6 +687,8 @@ async fn repl_task(
 #[instrument(level = "info", skip_all)]
 async fn handle_repl_conn(
     max_frame_bytes: usize,
+    clock_skew_warning: Duration,
+    clock_skew_tolerance: Duration,
     tcpstream: TcpStream,
     client_address: SocketAddr,
     tls_parms: SslAcceptor,
@@ -573,9 +715,20 @@ async fn handle_repl_conn(
 
     while let Some(codec_msg) = r.next().await {
         match codec_msg {
-            Ok(ConsumerRequest::Ping) => {
+            Ok(ConsumerRequest::Ping { consumer_time }) => {
                 debug!("consumer requested ping");
-                if let Err(err) = w.send(SupplierResponse::Pong).await {
+                let supplier_time = duration_from_epoch_now();
+                // The consumer refuses to proceed if the skew exceeds the tolerance, so we
+                // only need to report it here.
+                let _ = repl_report_clock_skew(
+                    &idms,
+                    &client_address.to_string(),
+                    clock_skew_ms(consumer_time, supplier_time),
+                    clock_skew_warning,
+                    clock_skew_tolerance,
+                );
+
+                if let Err(err) = w.send(SupplierResponse::Pong { supplier_time }).await {
                     error!(?err, "supplier encode error, unable to continue.");
                     break;
                 }
@@ -626,6 +779,23 @@ async fn handle_repl_conn(
     debug!(?client_address, "replication client disconnected 🛬");
 }
 
+async fn repl_get_identity(
+    idms: &IdmServer,
+    domain_name: &str,
+) -> Result<(PKey<Private>, X509, Vec<X509>), OperationError> {
+    let ct = duration_from_epoch_now();
+    let mut idms_prox_write = idms.proxy_write(ct).await?;
+
+    let (server_key, server_cert) = idms_prox_write
+        .qs_write
+        .supplier_get_key_cert(domain_name)?;
+    let trust_anchors = idms_prox_write.qs_write.supplier_get_trust_anchors()?;
+
+    idms_prox_write
+        .commit()
+        .map(|()| (server_key, server_cert, trust_anchors))
+}
+
 async fn repl_acceptor(
     listener: TcpListener,
     idms: Arc<IdmServer>,
@@ -644,6 +814,8 @@ async fn repl_acceptor(
         max_frame_bytes,
         task_poll_interval: repl_config.get_task_poll_interval(),
         replica_connect_timeout,
+        clock_skew_warning: repl_config.get_clock_skew_warning(),
+        clock_skew_tolerance: repl_config.get_clock_skew_tolerance(),
     };
 
     // Setup a broadcast to control our tasks.
@@ -689,18 +861,10 @@ async fn repl_acceptor(
         // Now we can start to re-load configurations and setup our client tasks
         // as well.
 
-        // Get the private key / cert.
-        let res = {
-            let ct = duration_from_epoch_now();
-            idms.proxy_write(ct).await.and_then(|mut idms_prox_write| {
-                idms_prox_write
-                    .qs_write
-                    .supplier_get_key_cert(&domain_name)
-                    .and_then(|res| idms_prox_write.commit().map(|()| res))
-            })
-        };
+        // Get the private key / cert, and the anchors that issue the identities of our domain.
+        let res = repl_get_identity(&idms, &domain_name).await;
 
-        let (server_key, server_cert) = match res {
+        let (server_key, server_cert, trust_anchors) = match res {
             Ok(r) => r,
             Err(err) => {
                 error!(?err, "CRITICAL: Unable to access supplier certificate/key.");
@@ -724,8 +888,8 @@ async fn repl_acceptor(
                 RepNodeConfig::MutualPull {
                     partner_cert: consumer_cert,
                     automatic_refresh: _,
-                }
-                | RepNodeConfig::AllowPull { consumer_cert } => {
+                } => client_certs.extend(consumer_cert.iter().cloned()),
+                RepNodeConfig::AllowPull { consumer_cert } => {
                     client_certs.push(consumer_cert.clone())
                 }
                 RepNodeConfig::Pull {
@@ -750,6 +914,7 @@ async fn repl_acceptor(
                         server_key.clone(),
                         server_cert.clone(),
                         supplier_cert.clone(),
+                        trust_anchors.clone(),
                         consumer_conn_settings.clone(),
                         task_rx,
                         *automatic_refresh,
@@ -789,7 +954,7 @@ async fn repl_acceptor(
         };
 
         // ⚠️  CRITICAL - ensure that the cert store only has client certs from
-        // the repl map added.
+        // the repl map and the trust anchors of our domain added.
         let cert_store = tls_builder.cert_store_mut();
         for client_cert in client_certs.into_iter() {
             if let Err(err) = cert_store.add_cert(client_cert.clone()) {
@@ -799,6 +964,28 @@ async fn repl_acceptor(
             }
         }
 
+        for trust_anchor in trust_anchors.iter() {
+            if let Err(err) = cert_store.add_cert(trust_anchor.clone()) {
+                error!(?err, "CRITICAL, unable to add replication trust anchors.");
+                sleep(retry_timeout).await;
+                continue 'event;
+            }
+        }
+
+        // Pinned client certs may have been issued by a trust anchor, so they must be
+        // trusted by themself rather than requiring the chain to a self signed root.
+        if let Err(err) = tls_builder
+            .verify_param_mut()
+            .set_flags(X509VerifyFlags::PARTIAL_CHAIN)
+        {
+            error!(
+                ?err,
+                "CRITICAL, unable to allow pinned client certificates."
+            );
+            sleep(retry_timeout).await;
+            continue;
+        }
+
         // ⚠️  CRITICAL - Both verifications here are needed. PEER requests
         // the client cert to be sent. FAIL_IF_NO_PEER_CERT triggers an
         // error if the cert is NOT present. FAIL_IF_NO_PEER_CERT on its own
@@ -809,6 +996,10 @@ async fn repl_acceptor(
 
         let tls_acceptor = tls_builder.build();
 
+        let mut identity_check = interval(REPL_IDENTITY_CHECK_INTERVAL);
+        // The first tick completes immediately.
+        identity_check.tick().await;
+
         loop {
             // This is great to diagnose when spans are entered or present and they capture
             // things incorrectly.
@@ -895,6 +1086,21 @@ async fn repl_acceptor(
                         }
                     }
                 }
+                _ = identity_check.tick() => {
+                    // Renew our identity if it is near expiry, and reload if it or the trust
+                    // anchors changed so that partners issued from a new anchor are accepted.
+                    match repl_get_identity(&idms, &domain_name).await {
+                        Ok((_, check_cert, check_anchors)) => {
+                            if check_cert != server_cert || check_anchors != trust_anchors {
+                                info!("Replication identity or trust anchors changed, reloading");
+                                continue 'event;
+                            }
+                        }
+                        Err(err) => {
+                            error!(?err, "Unable to check replication identity");
+                        }
+                    }
+                }
                 // Handle accepts.
                 // Handle *reloads*
                 /*
@@ -911,7 +1117,7 @@ async fn repl_acceptor(
                             // We don't care about the join handle here - once a client connects
                             // it sticks to whatever ssl settings it had at launch.
                             tokio::spawn(
-                                handle_repl_conn(max_frame_bytes, tcpstream, client_socket_addr, clone_tls_acceptor, clone_idms)
+                                handle_repl_conn(max_frame_bytes, consumer_conn_settings.clock_skew_warning, consumer_conn_settings.clock_skew_tolerance, tcpstream, client_socket_addr, clone_tls_acceptor, clone_idms)
                             );
                         }
                         Err(e) => {
@@ -0,0 +1,321 @@
+//! Scheduled reports for administrators. Each configured report is generated on its cron
+//! schedule, rendered as html or csv, and attached to a mail that is sent to the members of its
+//! recipient groups. Every generation is recorded as an audit event so that there is a history
+//! of what was sent, and to whom.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use askama::Template;
+use chrono::Utc;
+use cron::Schedule;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use tokio::time::{sleep, Duration};
+
+use kanidmd_lib::idm::audit::AuditEvent;
+use kanidmd_lib::idm::report::Report;
+use kanidmd_lib::idm::server::IdmServer;
+use kanidmd_lib::prelude::{duration_from_epoch_now, OperationError};
+
+use crate::config::{ReportConfig, ReportFormat, ReportKind};
+use crate::interval::parse_schedule;
+use crate::mail::{mail_time, MailAttachment, Mailer, ReportMail};
+use crate::CoreAction;
+
+#[derive(Template)]
+#[template(path = "mail/report.html")]
+struct ReportHtml<'a> {
+    name: &'a str,
+    time: String,
+    report: &'a Report,
+}
+
+struct ScheduledReport {
+    config: ReportConfig,
+    schedule: Schedule,
+}
+
+pub(crate) struct ReportActor {
+    idms: Arc<IdmServer>,
+    mailer: Arc<Mailer>,
+    repl_stale_threshold: Option<Duration>,
+}
+
+/// Quote a csv field if it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_csv(report: &Report) -> String {
+    std::iter::once(&report.columns)
+        .chain(report.rows.iter())
+        .map(|row| {
+            let mut line = row
+                .iter()
+                .map(|value| csv_field(value))
+                .collect::<Vec<_>>()
+                .join(",");
+            line.push_str("\r\n");
+            line
+        })
+        .collect()
+}
+
+fn render_attachment(
+    config: &ReportConfig,
+    report: &Report,
+    time: OffsetDateTime,
+) -> Result<MailAttachment, OperationError> {
+    let (content, content_type) = match config.format {
+        ReportFormat::Html => {
+            let html = ReportHtml {
+                name: &config.name,
+                time: mail_time(time),
+                report,
+            }
+            .render()
+            .map_err(|err| {
+                error!(?err, "Unable to render report");
+                OperationError::KG005MailDeliveryFailed
+            })?;
+            (html, "text/html; charset=utf-8")
+        }
+        ReportFormat::Csv => (render_csv(report), "text/csv; charset=utf-8"),
+    };
+
+    Ok(MailAttachment {
+        filename: format!("{}-{}.{}", config.name, time.date(), config.format),
+        content_type,
+        content: content.into_bytes(),
+    })
+}
+
+impl ReportActor {
+    // Allow this because result is the only way to map and ? to bubble up, but we aren't
+    // returning an op-error here because this is in early start up.
+    #[allow(clippy::result_unit_err)]
+    pub fn start(
+        idms: Arc<IdmServer>,
+        mailer: Arc<Mailer>,
+        reports: &[ReportConfig],
+        repl_stale_threshold: Option<Duration>,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> Result<tokio::task::JoinHandle<()>, ()> {
+        let reports = reports
+            .iter()
+            .map(|config| {
+                parse_schedule(&config.schedule, &format!("Report {}", config.name)).map(
+                    |schedule| ScheduledReport {
+                        config: config.clone(),
+                        schedule,
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let actor = ReportActor {
+            idms,
+            mailer,
+            repl_stale_threshold,
+        };
+
+        Ok(tokio::spawn(async move {
+            let mut last_run = Utc::now();
+
+            loop {
+                // Reports that were due while a previous report was generating are run at once.
+                let from = last_run.max(Utc::now());
+                let upcoming: Vec<_> = reports
+                    .iter()
+                    .filter_map(|report| {
+                        report
+                            .schedule
+                            .after(&from)
+                            .next()
+                            .map(|next_time| (next_time, report))
+                    })
+                    .collect();
+
+                let Some(next_time) = upcoming.iter().map(|(next_time, _)| *next_time).min() else {
+                    info!("No reports are scheduled to run again");
+                    break;
+                };
+
+                let wait = (next_time - Utc::now()).to_std().unwrap_or_default();
+                debug!(%next_time, "Next report run");
+
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                        }
+                    }
+                    _ = sleep(wait) => {
+                        for (_, report) in upcoming.iter().filter(|(t, _)| *t == next_time) {
+                            if let Err(err) = actor.run_report(&report.config).await {
+                                error!(?err, report = %report.config.name, "Unable to generate report");
+                            }
+                        }
+                        last_run = next_time;
+                    }
+                }
+            }
+
+            info!("Stopped {}", super::TaskName::ReportActor);
+        }))
+    }
+
+    fn replication_health_report(&self) -> Report {
+        let mut report = Report::new("Replication health", &["metric", "value"]);
+        let now = duration_from_epoch_now();
+
+        let Some(repl) = self.idms.server_metrics().replication else {
+            report
+                .summary
+                .push("This server has not replicated from a supplier.".to_string());
+            return report;
+        };
+
+        let since_success = now.saturating_sub(repl.last_success);
+
+        match self.repl_stale_threshold {
+            Some(threshold) if since_success > threshold => report.summary.push(format!(
+                "Replication is degraded, the last successful replication was {}s ago.",
+                since_success.as_secs()
+            )),
+            _ => report.summary.push("Replication is healthy.".to_string()),
+        }
+
+        report.rows.push(vec![
+            "last_successful_replication".to_string(),
+            mail_time(OffsetDateTime::UNIX_EPOCH + repl.last_success),
+        ]);
+        report.rows.push(vec![
+            "seconds_since_last_success".to_string(),
+            since_success.as_secs().to_string(),
+        ]);
+        report.rows.push(vec![
+            "lag_seconds".to_string(),
+            repl.lag.as_secs().to_string(),
+        ]);
+        if let Some(threshold) = self.repl_stale_threshold {
+            report.rows.push(vec![
+                "stale_threshold_seconds".to_string(),
+                threshold.as_secs().to_string(),
+            ]);
+        }
+
+        report
+    }
+
+    async fn run_report(&self, config: &ReportConfig) -> Result<(), OperationError> {
+        let now = OffsetDateTime::now_utc();
+
+        let (report, recipients) = {
+            let mut idms_prox_read = self.idms.proxy_read().await?;
+
+            let report = match config.kind {
+                ReportKind::SecurityPosture => idms_prox_read.security_posture_report()?,
+                ReportKind::StaleAccounts => {
+                    idms_prox_read.stale_accounts_report(now - config.stale_after())?
+                }
+                ReportKind::ReplicationHealth => self.replication_health_report(),
+                ReportKind::UpcomingExpiries => {
+                    idms_prox_read.upcoming_expiries_report(now, now + config.expiry_window())?
+                }
+            };
+
+            // A person in several of the recipient groups is only sent the report once.
+            let mut recipients = BTreeMap::new();
+            for group in config.recipients.iter() {
+                match idms_prox_read.report_recipients(group) {
+                    Ok(members) => {
+                        recipients.extend(members.into_iter().map(|member| (member.uuid, member)))
+                    }
+                    Err(err) => {
+                        error!(?err, %group, report = %config.name, "Unable to find the members of a report recipient group")
+                    }
+                }
+            }
+
+            (report, recipients)
+        };
+
+        let attachment = render_attachment(config, &report, now)?;
+        let branding = self.mailer.branding(&self.idms.domain_read());
+        let subject = format!("report {}", config.name);
+
+        let mut delivered = Vec::with_capacity(recipients.len());
+
+        for recipient in recipients.values() {
+            let body = ReportMail {
+                recipient,
+                name: &config.name,
+                title: &report.title,
+                time: mail_time(now),
+                summary: &report.summary,
+            };
+
+            // A failure for one recipient shouldn't prevent the others receiving the report.
+            match self
+                .mailer
+                .send_with_attachment(
+                    &branding,
+                    &recipient.displayname,
+                    &recipient.mail,
+                    &subject,
+                    &body,
+                    Some(&attachment),
+                )
+                .await
+            {
+                Ok(()) => delivered.push(recipient.spn.clone()),
+                Err(err) => {
+                    error!(?err, uuid = ?recipient.uuid, report = %config.name, "Unable to send report")
+                }
+            }
+        }
+
+        info!(
+            report = %config.name,
+            rows = report.rows.len(),
+            recipients = delivered.len(),
+            "Generated report"
+        );
+
+        self.idms.submit_audit_event(AuditEvent::ReportGenerated {
+            report: config.name.clone(),
+            kind: config.kind.to_string(),
+            rows: report.rows.len(),
+            recipients: delivered,
+            time: now,
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_csv;
+    use kanidmd_lib::idm::report::Report;
+
+    #[test]
+    fn test_report_render_csv() {
+        let mut report = Report::new("Test", &["spn", "label"]);
+        report.rows.push(vec![
+            "demo@example.com".to_string(),
+            "laptop, \"work\"".to_string(),
+        ]);
+
+        assert_eq!(
+            render_csv(&report),
+            "spn,label\r\ndemo@example.com,\"laptop, \"\"work\"\"\"\r\n"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Tenants are additional domains that are served by this server. Each tenant has its own
+//! database, query server and idm server, so its accounts, groups, oauth2 clients and keys are
+//! isolated from every other domain. The https server directs requests to a tenant by the
+//! host that they were sent to.
+
+use std::sync::Arc;
+
+use kanidmd_lib::idm::ldap::LdapServer;
+use kanidmd_lib::schema::Schema;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task;
+use tracing::{Instrument, Span};
+
+use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
+use crate::audit::AuditStore;
+use crate::config::{Configuration, TenantConfig};
+use crate::interval::IntervalActor;
+use crate::mail::Mailer;
+use crate::webhook::WebhookActor;
+use crate::{
+    auditd_loop, delayed_action_loop, load_geoip, setup_backend, setup_qs_idms, CoreAction,
+    TaskName,
+};
+
+pub(crate) struct TenantServer {
+    /// The host that requests to this tenant are sent to.
+    pub(crate) host: String,
+    pub(crate) config: Configuration,
+    pub(crate) qe_r_ref: &'static QueryServerReadV1,
+    pub(crate) qe_w_ref: &'static QueryServerWriteV1,
+}
+
+impl TenantServer {
+    /// Start the query server, idm server and actors of this tenant. The actors stop when
+    /// the server is shutdown.
+    pub(crate) async fn start(
+        config: &Configuration,
+        tenant: &TenantConfig,
+        config_test: bool,
+        broadcast_tx: &broadcast::Sender<CoreAction>,
+    ) -> Result<(Self, Vec<(TaskName, task::JoinHandle<()>)>), ()> {
+        let host = tenant.host().map_err(|err| {
+            error!(%err, "Invalid tenant configuration");
+        })?;
+        let config = config.tenant_configuration(tenant);
+
+        let span = info_span!("tenant", domain = %config.domain);
+
+        Self::start_inner(host, config, config_test, broadcast_tx)
+            .instrument(span)
+            .await
+    }
+
+    async fn start_inner(
+        host: String,
+        config: Configuration,
+        config_test: bool,
+        broadcast_tx: &broadcast::Sender<CoreAction>,
+    ) -> Result<(Self, Vec<(TaskName, task::JoinHandle<()>)>), ()> {
+        info!(%host, "Starting tenant");
+        // The tasks of this tenant log within its span.
+        let span = Span::current();
+
+        let schema = Schema::new().map_err(|err| {
+            error!(?err, "Failed to setup in memory schema");
+        })?;
+
+        let be = setup_backend(&config, &schema).map_err(|err| {
+            error!(?err, "Failed to setup BE");
+        })?;
+
+        let (_qs, idms, idms_delayed, idms_audit) =
+            setup_qs_idms(be, schema, &config).await.map_err(|err| {
+                error!(?err, "Unable to setup query server or idm server");
+            })?;
+
+        let ldap = LdapServer::new(&idms).await.map_err(|err| {
+            error!(?err, "Unable to start LdapServer");
+        })?;
+
+        let audit = AuditStore::new(&config.audit).map_err(|err| {
+            error!(?err, "Unable to open audit store");
+        })?;
+
+        if config.audit.activity {
+            idms.audit_activity_enable();
+        }
+
+        if let Some(geoip) = &config.geoip {
+            let geoip = load_geoip(geoip).map_err(|err| {
+                error!(%err, "Unable to load GeoIP database");
+            })?;
+            idms.geoip_enable(geoip).map_err(|err| {
+                error!(?err, "Unable to enable GeoIP lookups");
+            })?;
+        }
+
+        let mailer = config
+            .smtp
+            .as_ref()
+            .map(|smtp| Mailer::new(smtp, &config.origin))
+            .transpose()
+            .map_err(|err| {
+                error!(%err, "Unable to configure smtp relay");
+            })?
+            .map(Arc::new);
+
+        let idms_arc = Arc::new(idms);
+        let audit_arc = Arc::new(audit);
+
+        let qe_r_ref =
+            QueryServerReadV1::start_static(idms_arc.clone(), Arc::new(ldap), audit_arc.clone());
+        let qe_w_ref = QueryServerWriteV1::start_static(idms_arc.clone(), mailer);
+
+        let delayed_handle = task::spawn(
+            delayed_action_loop(idms_delayed, qe_w_ref, false, broadcast_tx.subscribe())
+                .instrument(span.clone()),
+        );
+
+        let (webhook_tx, webhook_rx) = mpsc::unbounded_channel();
+
+        let auditd_handle = task::spawn(
+            auditd_loop(
+                idms_audit,
+                audit_arc.clone(),
+                webhook_tx,
+                None,
+                broadcast_tx.subscribe(),
+            )
+            .instrument(span),
+        );
+
+        let webhook_handle = WebhookActor::start(
+            idms_arc,
+            audit_arc.clone(),
+            webhook_rx,
+            broadcast_tx.subscribe(),
+        )?;
+
+        let interval_handle =
+            IntervalActor::start(qe_w_ref, false, &config.changelog, broadcast_tx.subscribe());
+
+        let mut handles = vec![
+            (TaskName::IntervalActor, interval_handle),
+            (TaskName::DelayedActionActor, delayed_handle),
+            (TaskName::AuditdActor, auditd_handle),
+            (TaskName::WebhookActor, webhook_handle),
+        ];
+
+        if !config_test {
+            handles.push((
+                TaskName::AuditExportActor,
+                IntervalActor::start_audit_export(
+                    audit_arc,
+                    &config.audit,
+                    broadcast_tx.subscribe(),
+                )?,
+            ));
+        }
+
+        Ok((
+            TenantServer {
+                host,
+                config,
+                qe_r_ref,
+                qe_w_ref,
+            },
+            handles,
+        ))
+    }
+}
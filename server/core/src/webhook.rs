@@ -0,0 +1,225 @@
+//! Delivery of entry change events to webhooks. Each event is signed with the secret of the
+//! webhook using HMAC-SHA256 so that the receiver can verify it was sent by this server. Events
+//! that can't be delivered are retried with an exponential backoff, and the status of each
+//! delivery is recorded in the audit store so that it can be inspected.
+
+use std::sync::Arc;
+
+use kanidm_proto::internal::{OperationError, WebhookDelivery, WebhookDeliveryState};
+use kanidmd_lib::idm::audit::AuditEvent;
+use kanidmd_lib::idm::server::IdmServer;
+use kanidmd_lib::idm::webhook::WebhookDispatch;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinSet;
+use tokio::time::{sleep, Duration};
+
+use crate::audit::AuditStore;
+use crate::CoreAction;
+
+/// The number of times delivery of an event is attempted before it is considered failed.
+const WEBHOOK_DELIVERY_ATTEMPTS: u32 = 5;
+
+/// The delay in seconds before the first retry. This doubles for each following retry.
+const WEBHOOK_RETRY_DELAY: u64 = 5;
+
+const WEBHOOK_REQUEST_TIMEOUT: u64 = 10;
+
+pub(crate) const WEBHOOK_SIGNATURE_HEADER: &str = "X-Kanidm-Signature";
+pub(crate) const WEBHOOK_DELIVERY_HEADER: &str = "X-Kanidm-Delivery";
+pub(crate) const WEBHOOK_EVENT_HEADER: &str = "X-Kanidm-Event";
+
+/// Sign the body of an event with the secret of the webhook, returning the signature as it is
+/// sent in the signature header.
+pub(crate) fn webhook_signature(secret: &str, body: &[u8]) -> Result<String, OperationError> {
+    let signature = PKey::hmac(secret.as_bytes())
+        .and_then(|key| {
+            let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+            signer.update(body)?;
+            signer.sign_to_vec()
+        })
+        .map_err(|err| {
+            error!(?err, "Unable to sign webhook event");
+            OperationError::CryptographyError
+        })?;
+
+    Ok(format!("sha256={}", hex::encode(signature)))
+}
+
+pub(crate) struct WebhookActor {
+    idms: Arc<IdmServer>,
+    audit: Arc<AuditStore>,
+    client: reqwest::Client,
+}
+
+impl WebhookActor {
+    pub fn start(
+        idms: Arc<IdmServer>,
+        audit: Arc<AuditStore>,
+        mut webhook_rx: UnboundedReceiver<AuditEvent>,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> Result<tokio::task::JoinHandle<()>, ()> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(WEBHOOK_REQUEST_TIMEOUT))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|err| {
+                error!(?err, "Unable to build webhook http client");
+            })?;
+
+        let actor = WebhookActor {
+            idms,
+            audit,
+            client,
+        };
+
+        Ok(tokio::spawn(async move {
+            // Deliveries that are still being retried are abandoned at shutdown.
+            let mut deliveries = JoinSet::new();
+
+            loop {
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                        }
+                    }
+                    event = webhook_rx.recv() => {
+                        let Some(event) = event else {
+                            // Channel has closed, stop the task.
+                            break
+                        };
+                        match actor.dispatches(&event).await {
+                            Ok(dispatches) => {
+                                for dispatch in dispatches {
+                                    deliveries.spawn(deliver(
+                                        actor.client.clone(),
+                                        actor.audit.clone(),
+                                        dispatch,
+                                    ));
+                                }
+                            }
+                            Err(err) => {
+                                error!(?err, "Unable to determine webhooks for event");
+                            }
+                        }
+                    }
+                    Some(_) = deliveries.join_next() => {}
+                }
+            }
+
+            info!("Stopped {}", super::TaskName::WebhookActor);
+        }))
+    }
+
+    async fn dispatches(&self, event: &AuditEvent) -> Result<Vec<WebhookDispatch>, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        idms_prox_read.webhook_dispatches(event)
+    }
+}
+
+async fn deliver(client: reqwest::Client, audit: Arc<AuditStore>, dispatch: WebhookDispatch) {
+    let WebhookDispatch {
+        webhook,
+        url,
+        secret,
+        payload,
+    } = dispatch;
+
+    let mut delivery = WebhookDelivery {
+        id: payload.id,
+        webhook,
+        action: payload.action,
+        target: payload.target,
+        time: payload.time,
+        attempts: 0,
+        state: WebhookDeliveryState::Pending,
+        last_error: None,
+    };
+
+    let signed = serde_json::to_vec(&payload)
+        .map_err(|err| {
+            error!(?err, "Unable to serialise webhook event");
+            OperationError::SerdeJsonError
+        })
+        .and_then(|body| webhook_signature(&secret, &body).map(|signature| (body, signature)));
+
+    let (body, signature) = match signed {
+        Ok(signed) => signed,
+        Err(err) => {
+            delivery.state = WebhookDeliveryState::Failed;
+            delivery.last_error = Some(format!("{:?}", err));
+            record(&audit, &delivery);
+            return;
+        }
+    };
+
+    for attempt in 0..WEBHOOK_DELIVERY_ATTEMPTS {
+        if attempt > 0 {
+            sleep(Duration::from_secs(WEBHOOK_RETRY_DELAY << (attempt - 1))).await;
+        }
+
+        delivery.attempts += 1;
+
+        let result = client
+            .post(url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_DELIVERY_HEADER, delivery.id.to_string())
+            .header(WEBHOOK_EVENT_HEADER, delivery.action.to_string())
+            .header(WEBHOOK_SIGNATURE_HEADER, &signature)
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        match result {
+            Ok(_) => {
+                delivery.state = WebhookDeliveryState::Delivered;
+                delivery.last_error = None;
+                record(&audit, &delivery);
+                return;
+            }
+            Err(err) => {
+                warn!(
+                    ?err,
+                    ?webhook,
+                    delivery = ?delivery.id,
+                    attempt = delivery.attempts,
+                    "Unable to deliver webhook event"
+                );
+                delivery.last_error = Some(err.to_string());
+            }
+        }
+
+        if delivery.attempts < WEBHOOK_DELIVERY_ATTEMPTS {
+            record(&audit, &delivery);
+        }
+    }
+
+    error!(?webhook, delivery = ?delivery.id, "Webhook event was not delivered, giving up");
+    delivery.state = WebhookDeliveryState::Failed;
+    record(&audit, &delivery);
+}
+
+fn record(audit: &AuditStore, delivery: &WebhookDelivery) {
+    if let Err(err) = audit.record_webhook_delivery(delivery) {
+        error!(?err, "Unable to record webhook delivery status");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::webhook_signature;
+
+    #[test]
+    fn test_webhook_signature() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            webhook_signature("Jefe", b"what do ya want for nothing?").as_deref(),
+            Ok("sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+    }
+}
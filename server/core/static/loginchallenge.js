@@ -0,0 +1,49 @@
+/**
+ * Solves the proof of work challenge on the login form.
+ *
+ * After an account has failed to login several times, the server asks for a number whose
+ * SHA-256 hash, when appended to the challenge nonce, begins with the requested number of zero
+ * bits. Once it is found the solution is placed in the form and the login button is enabled.
+ *
+ * @function solve_login_challenge
+ */
+
+function leading_zero_bits(digest) {
+    let bits = 0;
+    for (const byte of digest) {
+        if (byte === 0) {
+            bits += 8;
+        } else {
+            bits += Math.clz32(byte) - 24;
+            break;
+        }
+    }
+    return bits;
+}
+
+async function solve_login_challenge() {
+    const nonce = document.getElementById("pow_nonce");
+    const difficulty = parseInt(nonce.dataset.difficulty, 10);
+    const encoder = new TextEncoder();
+
+    for (let counter = 0; ; counter++) {
+        const digest = await crypto.subtle.digest("SHA-256", encoder.encode(`${nonce.value}:${counter}`));
+        if (leading_zero_bits(new Uint8Array(digest)) >= difficulty) {
+            document.getElementById("pow_solution").value = counter.toString();
+            break;
+        }
+    }
+
+    document.getElementById("pow_status").classList.add("d-none");
+    document.getElementById("login_begin").disabled = false;
+}
+
+try {
+    addEventListener("load", () => {
+        solve_login_challenge().catch((error) => {
+            console.error(`Failed to solve login challenge: ${error}`);
+        });
+    });
+} catch (error) {
+    console.error(`Failed to add load-time event listener for login challenge: ${error}`);
+}
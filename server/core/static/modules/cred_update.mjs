@@ -9,12 +9,14 @@ function setupInteractivePwdFormListeners() {
     function markPwdCheckValid() {
         new_pwd_check.classList.remove("is-invalid");
         new_pwd_check.classList.add("is-valid");
+        new_pwd_check.removeAttribute("aria-invalid");
         pwd_submit.disabled = false;
     }
 
     function markPwdCheckInvalid() {
         new_pwd_check.classList.add("is-invalid");
         new_pwd_check.classList.remove("is-valid");
+        new_pwd_check.setAttribute("aria-invalid", "true");
         pwd_submit.disabled = true;
     }
 
@@ -30,6 +32,7 @@ function setupInteractivePwdFormListeners() {
             }
         }
         new_pwd.classList.remove("is-invalid");
+        new_pwd.removeAttribute("aria-invalid");
     });
 
     new_pwd_check.addEventListener("input", () => {
@@ -0,0 +1,56 @@
+/**
+ * Offers the user's passkeys in the autofill of the username field on the login page.
+ *
+ * This function retrieves the discoverable credential request options from the DOM and
+ * starts a conditional mediation request, so the browser shows the passkeys for this site
+ * alongside saved usernames instead of in a modal prompt. When the user selects a passkey the
+ * assertion is encoded to Base64 and the form is submitted with the credential data.
+ *
+ * @function passkey_autofill
+ */
+
+async function passkey_autofill() {
+    if (
+        !window.PublicKeyCredential ||
+        !PublicKeyCredential.isConditionalMediationAvailable ||
+        !(await PublicKeyCredential.isConditionalMediationAvailable())
+    ) {
+        // The browser can't offer passkeys in autofill, the user can still login with
+        // their username.
+        return;
+    }
+
+    let credentialRequestOptions = JSON.parse(document.getElementById("data").textContent);
+    credentialRequestOptions.publicKey.challenge = Base64.toUint8Array(credentialRequestOptions.publicKey.challenge);
+    credentialRequestOptions.publicKey.allowCredentials?.forEach(function (listItem) {
+        listItem.id = Base64.toUint8Array(listItem.id);
+    });
+
+    const assertion = await navigator.credentials.get({
+        mediation: "conditional",
+        publicKey: credentialRequestOptions.publicKey,
+    });
+
+    document.getElementById("cred").value = JSON.stringify({
+        id: assertion.id,
+        rawId: Base64.fromUint8Array(new Uint8Array(assertion.rawId), true),
+        type: assertion.type,
+        response: {
+            authenticatorData: Base64.fromUint8Array(new Uint8Array(assertion.response.authenticatorData), true),
+            clientDataJSON: Base64.fromUint8Array(new Uint8Array(assertion.response.clientDataJSON), true),
+            signature: Base64.fromUint8Array(new Uint8Array(assertion.response.signature), true),
+            userHandle: Base64.fromUint8Array(new Uint8Array(assertion.response.userHandle), true),
+        },
+    });
+    document.getElementById("cred-form").submit();
+}
+
+try {
+    addEventListener("load", () => {
+        passkey_autofill().catch((error) => {
+            console.error(`Failed to complete passkey autofill authentication: ${error}`);
+        });
+    });
+} catch (error) {
+    console.error(`Failed to add load-time event listener for passkey autofill: ${error}`);
+}
@@ -0,0 +1,40 @@
+/**
+ * Warns that a session with an idle timeout is about to end because it has not been used, and
+ * returns to the login page once it has ended.
+ *
+ * The navbar loads the warning with the number of seconds the session may remain unused. Each
+ * time it is loaded, including by the "Stay Signed In" button, the session is used and the
+ * countdown starts again.
+ *
+ * @function watch_session_idle
+ */
+
+const SESSION_IDLE_WARNING_SECS = 120;
+
+let session_idle_timers = [];
+
+function watch_session_idle(warning) {
+    session_idle_timers.forEach(clearTimeout);
+    session_idle_timers = [];
+
+    const remaining = parseInt(warning.dataset.idleRemaining, 10);
+    if (isNaN(remaining)) {
+        return;
+    }
+
+    const warn_after = Math.max(remaining - SESSION_IDLE_WARNING_SECS, 0);
+    session_idle_timers.push(
+        setTimeout(() => warning.classList.remove("d-none"), warn_after * 1000)
+    );
+    session_idle_timers.push(
+        setTimeout(() => window.location.assign("/ui/login"), remaining * 1000)
+    );
+}
+
+htmx.onLoad((content) => {
+    const warning =
+        content.id === "session_idle_warning" ? content : content.querySelector("#session_idle_warning");
+    if (warning) {
+        watch_session_idle(warning);
+    }
+});
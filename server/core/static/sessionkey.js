@@ -0,0 +1,126 @@
+/**
+ * Binds this browser's sessions to a key that can't leave the browser.
+ *
+ * A non-extractable ECDSA P-256 key pair is created once and kept in IndexedDB. A short lived
+ * proof signed by the key is kept in a cookie, and refreshed each minute while a page is open.
+ * Sessions that begin while the proof is present are bound to the key, and the server refuses
+ * them without a current proof, so a copied session cookie is useless on another device. Once the
+ * server has told us the id of our session, it is included in the proof so that the proof can't be
+ * used with another session from this browser.
+ *
+ * @function refresh_session_proof
+ */
+
+const SESSION_KEY_DB = "kanidm-session-key";
+const SESSION_KEY_STORE = "keys";
+const SESSION_KEY_ID = "session";
+const SESSION_PROOF_COOKIE = "session-proof";
+const BOUND_SESSION_ID_COOKIE = "bound-session-id";
+const SESSION_PROOF_TYP = "kanidm-session+jwt";
+const SESSION_PROOF_MAX_AGE = 300;
+const SESSION_PROOF_REFRESH_MS = 60 * 1000;
+
+function idb_result(request) {
+    return new Promise((resolve, reject) => {
+        request.onsuccess = () => resolve(request.result);
+        request.onerror = () => reject(request.error);
+    });
+}
+
+async function session_key() {
+    const open = indexedDB.open(SESSION_KEY_DB, 1);
+    open.onupgradeneeded = () => open.result.createObjectStore(SESSION_KEY_STORE);
+    const db = await idb_result(open);
+
+    const get_key = () => idb_result(db.transaction(SESSION_KEY_STORE).objectStore(SESSION_KEY_STORE).get(SESSION_KEY_ID));
+
+    const existing = await get_key();
+    if (existing) {
+        return existing;
+    }
+
+    const key = await crypto.subtle.generateKey({ name: "ECDSA", namedCurve: "P-256" }, false, ["sign"]);
+    try {
+        await idb_result(
+            db.transaction(SESSION_KEY_STORE, "readwrite").objectStore(SESSION_KEY_STORE).add(key, SESSION_KEY_ID)
+        );
+        return key;
+    } catch (error) {
+        // Another tab created the key first, use theirs so that both tabs agree.
+        return await get_key();
+    }
+}
+
+function base64url(bytes) {
+    return btoa(String.fromCharCode(...bytes)).replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+}
+
+function base64url_json(value) {
+    return base64url(new TextEncoder().encode(JSON.stringify(value)));
+}
+
+function get_cookie(name) {
+    const cookie = document.cookie.split("; ").find((cookie) => cookie.startsWith(`${name}=`));
+    return cookie ? cookie.substring(name.length + 1) : null;
+}
+
+function has_session_proof() {
+    return get_cookie(SESSION_PROOF_COOKIE) !== null;
+}
+
+async function refresh_session_proof() {
+    const key = await session_key();
+    const { kty, crv, x, y } = await crypto.subtle.exportKey("jwk", key.publicKey);
+
+    const header = base64url_json({ alg: "ES256", typ: SESSION_PROOF_TYP, jwk: { kty, crv, x, y } });
+    const sid = get_cookie(BOUND_SESSION_ID_COOKIE);
+    const claims = base64url_json(sid ? { iat: Math.floor(Date.now() / 1000), sid } : { iat: Math.floor(Date.now() / 1000) });
+    const signing_input = `${header}.${claims}`;
+
+    // WebCrypto signatures are the concatenated r and s values, as a JWS requires.
+    const signature = await crypto.subtle.sign(
+        { name: "ECDSA", hash: "SHA-256" },
+        key.privateKey,
+        new TextEncoder().encode(signing_input)
+    );
+
+    const secure = location.protocol === "https:" ? "; Secure" : "";
+    document.cookie = `${SESSION_PROOF_COOKIE}=${signing_input}.${base64url(new Uint8Array(signature))}; Path=/; Max-Age=${SESSION_PROOF_MAX_AGE}; SameSite=Lax${secure}`;
+}
+
+async function start_session_proof() {
+    if (!window.crypto?.subtle || !window.indexedDB) {
+        // Sessions in this browser won't be bound.
+        return;
+    }
+
+    const had_proof = has_session_proof();
+    await refresh_session_proof();
+
+    // If the proof expired while the browser was closed, a bound session was sent to the login
+    // page. Now that there is a proof again, try once more before asking for a login.
+    if (!had_proof && location.pathname === "/ui/login" && !sessionStorage.getItem(SESSION_PROOF_COOKIE)) {
+        sessionStorage.setItem(SESSION_PROOF_COOKIE, "retried");
+        location.reload();
+        return;
+    }
+    sessionStorage.removeItem(SESSION_PROOF_COOKIE);
+
+    setInterval(() => {
+        refresh_session_proof().catch((error) => {
+            console.error(`Failed to refresh session proof: ${error}`);
+        });
+    }, SESSION_PROOF_REFRESH_MS);
+
+    document.addEventListener("visibilitychange", () => {
+        if (document.visibilityState === "visible") {
+            refresh_session_proof().catch((error) => {
+                console.error(`Failed to refresh session proof: ${error}`);
+            });
+        }
+    });
+}
+
+start_session_proof().catch((error) => {
+    console.error(`Failed to start session proof: ${error}`);
+});
@@ -1,7 +1,17 @@
-/**  Queries the user's preferred colour scheme and returns the appropriate value.
+/** The key under which the theme of the web interface is stored, so that it can be applied
+ before the server has been asked for it.
+*/
+const THEME_STORAGE_KEY = "kanidm-theme";
+
+/**  Queries the user's preferred colour scheme and returns the appropriate value. If the person
+ or domain has chosen a theme, that is used in place of the browser preference.
  From https://getbootstrap.com/docs/5.3/customize/color-modes/#javascript
 */
 function getPreferredTheme() {
+    const theme = localStorage.getItem(THEME_STORAGE_KEY);
+    if (theme === "light" || theme === "dark") {
+        return theme;
+    }
     return window.matchMedia("(prefers-color-scheme: dark)").matches ? "dark" : "light";
 }
 
@@ -13,7 +23,73 @@ function updateColourScheme() {
     document.documentElement.setAttribute("data-bs-theme", theme);
 }
 
+/**  Asks the server for the theme of the person, or the domain default if they have not chosen
+ one, and applies it.
+ */
+function refreshTheme() {
+    fetch("/ui/api/theme", { credentials: "same-origin" })
+        .then((response) => (response.ok ? response.json() : null))
+        .then((theme) => {
+            if (theme) {
+                localStorage.setItem(THEME_STORAGE_KEY, theme);
+                updateColourScheme();
+            }
+        })
+        .catch((error) => console.debug(`refreshTheme failed -> ${error}`));
+}
+
+/**  Moves focus to the error summary of a form that was returned with validation errors, so
+ that the problems are announced by screen readers before the person continues.
+ */
+function focusErrorSummary() {
+    document.querySelector(".kanidm-error-summary")?.focus();
+}
+
+/** The key under which the announcements that have been dismissed in this browser are stored.
+*/
+const DISMISSED_ANNOUNCEMENTS_KEY = "kanidm-dismissed-announcements";
+
+function getDismissedAnnouncements() {
+    try {
+        const dismissed = JSON.parse(localStorage.getItem(DISMISSED_ANNOUNCEMENTS_KEY));
+        return Array.isArray(dismissed) ? dismissed : [];
+    } catch {
+        return [];
+    }
+}
+
+/**  Removes the announcements that have already been dismissed, so that they are not shown on
+ every page.
+ */
+function hideDismissedAnnouncements() {
+    const dismissed = getDismissedAnnouncements();
+    document.querySelectorAll(".kanidm-announcement").forEach((announcement) => {
+        if (dismissed.includes(announcement.dataset.announcementUuid)) {
+            announcement.remove();
+        }
+    });
+}
+
+/**  Remembers that an announcement was dismissed, so it stays hidden in this browser.
+ */
+function rememberDismissedAnnouncement(event) {
+    const uuid = event.target.dataset?.announcementUuid;
+    if (!uuid) {
+        return;
+    }
+    const dismissed = getDismissedAnnouncements();
+    dismissed.push(uuid);
+    localStorage.setItem(DISMISSED_ANNOUNCEMENTS_KEY, JSON.stringify(dismissed));
+}
+
 updateColourScheme();
+refreshTheme();
+hideDismissedAnnouncements();
+focusErrorSummary();
 window.matchMedia("(prefers-color-scheme: light)").addEventListener("change", updateColourScheme);
 window.matchMedia("(prefers-color-scheme: dark)").addEventListener("change", updateColourScheme);
 document.body.addEventListener("htmx:afterOnLoad", updateColourScheme);
+document.body.addEventListener("kanidmThemeChanged", refreshTheme);
+document.body.addEventListener("htmx:afterSettle", focusErrorSummary);
+document.body.addEventListener("htmx:afterSettle", hideDismissedAnnouncements);
+document.body.addEventListener("closed.bs.alert", rememberDismissedAnnouncement);
@@ -21,8 +21,9 @@ static ALLOC: dhat::Alloc = dhat::Alloc;
 use std::fs::{metadata, File};
 // This works on both unix and windows.
 use fs4::fs_std::FileExt;
+use kanidm_proto::internal::SelfTestStatus;
 use kanidm_proto::messages::ConsoleOutputMode;
-use sketching::otel::TracingPipelineGuard;
+use sketching::otel::{LogLevelReloadHandle, TracingPipelineGuard};
 use std::io::Read;
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::MetadataExt;
@@ -39,11 +40,13 @@ use kanidmd_core::admin::{
 };
 use kanidmd_core::config::{Configuration, ServerConfig};
 use kanidmd_core::{
-    backup_server_core, cert_generate_core, create_server_core, dbscan_get_id2entry_core,
-    dbscan_list_id2entry_core, dbscan_list_index_analysis_core, dbscan_list_index_core,
-    dbscan_list_indexes_core, dbscan_list_quarantined_core, dbscan_quarantine_id2entry_core,
-    dbscan_restore_quarantined_core, domain_rename_core, reindex_server_core, restore_server_core,
-    vacuum_server_core, verify_server_core,
+    backup_server_core, break_glass_codes_generate_core, cert_generate_core, config_validate_core,
+    create_server_core, dbscan_get_id2entry_core, dbscan_list_id2entry_core,
+    dbscan_list_index_analysis_core, dbscan_list_index_core, dbscan_list_indexes_core,
+    dbscan_list_quarantined_core, dbscan_quarantine_id2entry_core, dbscan_restore_quarantined_core,
+    doctor_core, domain_rename_core, migrate_sqlite_server_core, password_breach_filter_build_core,
+    reindex_server_core, restore_server_core, vacuum_server_core, verify_server_core, CoreHandle,
+    RestoreMode,
 };
 use sketching::tracing_forest::util::*;
 use tokio::net::UnixStream;
@@ -59,6 +62,14 @@ impl KanidmdOpt {
             KanidmdOpt::Server(sopt)
             | KanidmdOpt::CertGenerate(sopt)
             | KanidmdOpt::ConfigTest(sopt)
+            | KanidmdOpt::SelfTest(sopt)
+            | KanidmdOpt::Doctor(sopt)
+            | KanidmdOpt::Config {
+                commands: ConfigCommands::Validate(sopt),
+            }
+            | KanidmdOpt::Config {
+                commands: ConfigCommands::Reload(sopt),
+            }
             | KanidmdOpt::DbScan {
                 commands: DbScanOpt::ListIndexes(sopt),
             }
@@ -67,6 +78,18 @@ impl KanidmdOpt {
             }
             | KanidmdOpt::DbScan {
                 commands: DbScanOpt::ListIndexAnalysis(sopt),
+            }
+            | KanidmdOpt::Schema {
+                commands: SchemaCommands::Show(sopt),
+            }
+            | KanidmdOpt::KeyObject {
+                commands: KeyObjectCommands::List(sopt),
+            }
+            | KanidmdOpt::Maintenance {
+                commands:
+                    MaintenanceCommands::Enable(sopt)
+                    | MaintenanceCommands::Disable(sopt)
+                    | MaintenanceCommands::Status(sopt),
             } => sopt,
             KanidmdOpt::Database {
                 commands: DbCommands::Backup(bopt),
@@ -74,6 +97,9 @@ impl KanidmdOpt {
             KanidmdOpt::Database {
                 commands: DbCommands::Restore(ropt),
             } => &ropt.commonopts,
+            KanidmdOpt::Database {
+                commands: DbCommands::MigrateSqlite(mopt),
+            } => &mopt.commonopts,
             KanidmdOpt::DbScan {
                 commands: DbScanOpt::QuarantineId2Entry { commonopts, .. },
             }
@@ -85,7 +111,14 @@ impl KanidmdOpt {
             }
             | KanidmdOpt::ShowReplicationCertificate { commonopts }
             | KanidmdOpt::RenewReplicationCertificate { commonopts }
-            | KanidmdOpt::RefreshReplicationConsumer { commonopts, .. } => commonopts,
+            | KanidmdOpt::RefreshReplicationConsumer { commonopts, .. }
+            | KanidmdOpt::ReplicationStatus { commonopts }
+            | KanidmdOpt::KeyObject {
+                commands: KeyObjectCommands::Rotate { commonopts, .. },
+            }
+            | KanidmdOpt::KeyObject {
+                commands: KeyObjectCommands::Revoke { commonopts, .. },
+            } => commonopts,
             KanidmdOpt::RecoverAccount { commonopts, .. } => commonopts,
             KanidmdOpt::DbScan {
                 commands: DbScanOpt::ListIndex(dopt),
@@ -109,15 +142,20 @@ impl KanidmdOpt {
                 commands: DomainSettingsCmds::Remigrate { commonopts, .. },
             } => commonopts,
             KanidmdOpt::Database {
-                commands: DbCommands::Verify(sopt),
-            }
-            | KanidmdOpt::Database {
-                commands: DbCommands::Reindex(sopt),
+                commands: DbCommands::Analyze(sopt),
             } => sopt,
+            KanidmdOpt::Database {
+                commands: DbCommands::Verify(vopt),
+            } => &vopt.commonopts,
+            KanidmdOpt::Database {
+                commands: DbCommands::Reindex(ropt),
+            } => &ropt.commonopts,
             KanidmdOpt::Database {
                 commands: DbCommands::Vacuum(copt),
             } => copt,
             KanidmdOpt::HealthCheck(hcopt) => &hcopt.commonopts,
+            KanidmdOpt::BreakGlassCodesGenerate(bopt) => &bopt.commonopts,
+            KanidmdOpt::PasswordBreachFilterBuild(bopt) => &bopt.commonopts,
             KanidmdOpt::Version(copt) => copt,
         }
     }
@@ -292,6 +330,169 @@ async fn submit_admin_req(path: &str, req: AdminTaskRequest, output_mode: Consol
                 info!("domain_level  : {}", level);
             }
         },
+        Some(Ok(AdminTaskResponse::SelfTest { report })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "self_test": report
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                info!("self_test_status       : {}", report.status());
+                for item in report.items {
+                    info!("------------------------");
+                    info!("check                  : {}", item.check);
+                    info!("status                 : {}", item.status);
+                    info!("detail                 : {}", item.detail);
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::Reindex { status })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "reindex": status
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                info!("reindex_state          : {}", status.state);
+                info!(
+                    "attributes_completed   : {}/{}",
+                    status.completed, status.total
+                );
+                if let Some(attribute) = status.attribute {
+                    info!("attribute              : {}", attribute);
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::DatabaseAnalyze { report })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "database_analyze": report
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                info!("searches               : {}", report.searches);
+                info!("slow_searches          : {}", report.slow_searches);
+                info!("index_hits             : {}", report.index_hits);
+                info!("index_misses           : {}", report.index_misses);
+                info!("candidates_tested      : {}", report.candidates);
+                info!("results                : {}", report.results);
+                info!("search_time_ms         : {}", report.time_ms);
+                if report.recommendations.is_empty() {
+                    info!("No missing indexes were found");
+                }
+                for rec in report.recommendations {
+                    info!("------------------------");
+                    info!(
+                        "recommend_index        : {} {}",
+                        rec.attribute, rec.index_type
+                    );
+                    info!("searches               : {}", rec.searches);
+                    info!("candidates_tested      : {}", rec.candidates);
+                    info!("search_time_ms         : {}", rec.time_ms);
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::SchemaShow { report })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "schema": report
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                for attr in report.attributes {
+                    info!("------------------------");
+                    info!("attribute              : {}", attr.name);
+                    info!("uuid                   : {}", attr.uuid);
+                    info!("description            : {}", attr.description);
+                    info!("syntax                 : {}", attr.syntax);
+                    info!("multivalue             : {}", attr.multivalue);
+                    info!("unique                 : {}", attr.unique);
+                    info!("index                  : {}", attr.index.join(", "));
+                }
+                for class in report.classes {
+                    info!("------------------------");
+                    info!("class                  : {}", class.name);
+                    info!("uuid                   : {}", class.uuid);
+                    info!("description            : {}", class.description);
+                    info!("must                   : {}", class.must.join(", "));
+                    info!("may                    : {}", class.may.join(", "));
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::KeyObjectList { key_objects })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "key_objects": key_objects
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                for key_object in key_objects {
+                    info!("------------------------");
+                    info!("key_object             : {}", key_object.uuid);
+                    if let Some(name) = key_object.name {
+                        info!("name                   : {}", name);
+                    }
+                    for key in key_object.keys {
+                        info!(
+                            "key                    : {} {} {} valid_from={}",
+                            key.key_id, key.usage, key.status, key.valid_from
+                        );
+                    }
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::ReplicationStatus { report })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "replication_status": report
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                info!("trim_point             : {}", report.trim_point);
+                for server in report.servers {
+                    info!("------------------------");
+                    info!("server_uuid            : {}", server.server_uuid);
+                    info!("local                  : {}", server.local);
+                    info!("oldest_change          : {}", server.oldest_change);
+                    info!("newest_change          : {}", server.newest_change);
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::ReloadConfig { changed })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "changed": changed
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                info!("Configuration reloaded");
+                for setting in changed {
+                    info!("changed                : {}", setting);
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::MaintenanceMode { enabled })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "maintenance_mode": enabled
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                if enabled {
+                    info!("maintenance mode       : enabled, changes from clients are refused");
+                } else {
+                    info!("maintenance mode       : disabled");
+                }
+            }
+        },
         Some(Ok(AdminTaskResponse::Success)) => match output_mode {
             ConsoleOutputMode::JSON => {
                 eprintln!("\"success\"")
@@ -318,7 +519,7 @@ async fn submit_admin_req(path: &str, req: AdminTaskRequest, output_mode: Consol
 }
 
 /// Check what we're running as and various filesystem permissions.
-fn check_file_ownership(opt: &KanidmdParser) -> Result<(), ExitCode> {
+fn check_file_ownership(sconfig: &ServerConfig) -> Result<(), ExitCode> {
     // Get info about who we are.
     #[cfg(target_family = "unix")]
     let (cuid, ceuid) = {
@@ -341,10 +542,11 @@ fn check_file_ownership(opt: &KanidmdParser) -> Result<(), ExitCode> {
         (cuid, ceuid)
     };
 
-    if let Some(cfg_path) = opt.config_path() {
+    // This includes any files that were included by the configuration.
+    for cfg_path in sconfig.config_files() {
         #[cfg(target_family = "unix")]
         {
-            if let Some(cfg_meta) = match metadata(&cfg_path) {
+            if let Some(cfg_meta) = match metadata(cfg_path) {
                 Ok(m) => Some(m),
                 Err(e) => {
                     error!(
@@ -378,6 +580,149 @@ fn check_file_ownership(opt: &KanidmdParser) -> Result<(), ExitCode> {
     Ok(())
 }
 
+/// Check the TLS files from the configuration exist and have secure permissions.
+fn check_tls_file_permissions(sconfig: &ServerConfig) -> Result<(), ExitCode> {
+    if let Some(i_str) = &(sconfig.tls_chain) {
+        let i_path = PathBuf::from(i_str.as_str());
+        let i_meta = match metadata(&i_path) {
+            Ok(m) => m,
+            Err(e) => {
+                error!(
+                    "Unable to read metadata for TLS chain file '{}' - {:?}",
+                    &i_path.to_str().unwrap_or("invalid file path"),
+                    e
+                );
+                let diag = kanidm_lib_file_permissions::diagnose_path(&i_path);
+                info!(%diag);
+                return Err(ExitCode::FAILURE);
+            }
+        };
+        if !kanidm_lib_file_permissions::readonly(&i_meta) {
+            warn!("permissions on {} may not be secure. Should be readonly to running uid. This could be a security risk ...", i_str);
+        }
+    }
+
+    if let Some(i_str) = &(sconfig.tls_key) {
+        let i_path = PathBuf::from(i_str.as_str());
+
+        let i_meta = match metadata(&i_path) {
+            Ok(m) => m,
+            Err(e) => {
+                error!(
+                    "Unable to read metadata for TLS key file '{}' - {:?}",
+                    &i_path.to_str().unwrap_or("invalid file path"),
+                    e
+                );
+                let diag = kanidm_lib_file_permissions::diagnose_path(&i_path);
+                info!(%diag);
+                return Err(ExitCode::FAILURE);
+            }
+        };
+        if !kanidm_lib_file_permissions::readonly(&i_meta) {
+            warn!("permissions on {} may not be secure. Should be readonly to running uid. This could be a security risk ...", i_str);
+        }
+        #[cfg(not(target_os = "windows"))]
+        if i_meta.mode() & 0o007 != 0 {
+            warn!("WARNING: {} has 'everyone' permission bits in the mode. This could be a security risk ...", i_str);
+        }
+    }
+
+    if let Some(ca_dir) = &(sconfig.tls_client_ca) {
+        // check that the TLS client CA config option is what we expect
+        let ca_dir_path = PathBuf::from(&ca_dir);
+        if !ca_dir_path.exists() {
+            error!(
+                "TLS CA folder {} does not exist, server startup will FAIL!",
+                ca_dir
+            );
+            let diag = kanidm_lib_file_permissions::diagnose_path(&ca_dir_path);
+            info!(%diag);
+        }
+
+        let i_meta = match metadata(&ca_dir_path) {
+            Ok(m) => m,
+            Err(e) => {
+                error!("Unable to read metadata for '{}' - {:?}", ca_dir, e);
+                let diag = kanidm_lib_file_permissions::diagnose_path(&ca_dir_path);
+                info!(%diag);
+                return Err(ExitCode::FAILURE);
+            }
+        };
+        if !i_meta.is_dir() {
+            error!(
+                "ERROR: Refusing to run - TLS Client CA folder {} may not be a directory",
+                ca_dir
+            );
+            return Err(ExitCode::FAILURE);
+        }
+        if kanidm_lib_file_permissions::readonly(&i_meta) {
+            warn!("WARNING: TLS Client CA folder permissions on {} indicate it may not be RW. This could cause the server start up to fail!", ca_dir);
+        }
+        #[cfg(not(target_os = "windows"))]
+        if i_meta.mode() & 0o007 != 0 {
+            warn!("WARNING: TLS Client CA folder {} has 'everyone' permission bits in the mode. This could be a security risk ...", ca_dir);
+        }
+    }
+    Ok(())
+}
+
+/// The configuration file to read, which is either the path given on the command line or
+/// the default path if it exists.
+fn resolve_config_path(opt: &KanidmdParser) -> Option<PathBuf> {
+    let default_config_path = PathBuf::from(env!("KANIDM_SERVER_CONFIG_PATH"));
+
+    if let Some(p) = opt.config_path() {
+        Some(p)
+    } else {
+        // The user didn't ask for a file, lets check if the default path exists?
+        if default_config_path.exists() {
+            // It does, lets use it.
+            Some(default_config_path)
+        } else {
+            // No default config, and no config specified, lets assume the user
+            // has selected environment variables.
+            None
+        }
+    }
+}
+
+/// Re-read the configuration and apply the settings that can be changed while the server is
+/// running. If the configuration is invalid, the server continues with its current
+/// configuration.
+async fn reload_config(
+    sctx: &mut CoreHandle,
+    opt: &KanidmdParser,
+    log_level_handle: &LogLevelReloadHandle,
+) -> Result<Vec<String>, String> {
+    let result = async {
+        let sconfig = ServerConfig::new(resolve_config_path(opt))
+            .map_err(|err| format!("Unable to read the configuration: {:?}", err))?;
+
+        let mut config = sctx.configuration().clone();
+        config.update_reloadable(&sconfig);
+        let log_level = config.log_level;
+
+        let changed = sctx.reload_config(config).await?;
+
+        if let Err(err) = log_level_handle.set(log_level) {
+            error!(?err, "Unable to change the log level");
+        }
+
+        Ok(changed)
+    }
+    .await;
+
+    match &result {
+        Ok(changed) => info!(?changed, "Configuration reloaded"),
+        Err(reason) => error!(
+            %reason,
+            "Configuration reload failed, the current configuration is unchanged"
+        ),
+    }
+
+    result
+}
+
 // We have to do this because we can't use tracing until we've started the logging pipeline, and we can't start the logging pipeline until the tokio runtime's doing its thing.
 async fn start_daemon(
     opt: KanidmdParser,
@@ -387,7 +732,7 @@ async fn start_daemon(
     // if we have a server config and it has an OTEL URL, then we'll start the logging pipeline now.
 
     // TODO: only send to stderr when we're not in a TTY
-    let sub = match sketching::otel::start_logging_pipeline(
+    let (sub, log_level_handle) = match sketching::otel::start_reloadable_logging_pipeline(
         &sconfig.otel_grpc_url,
         sconfig.log_level.unwrap_or_default(),
         "kanidmd",
@@ -419,7 +764,7 @@ async fn start_daemon(
     // Start pre-run checks
 
     // Check the permissions of the files from the configuration.
-    if let Err(err) = check_file_ownership(&opt) {
+    if let Err(err) = check_file_ownership(&sconfig) {
         return err;
     };
 
@@ -485,7 +830,12 @@ async fn start_daemon(
     }
 
     config.update_db_arc_size(sconfig.get_db_arc_size());
+    config.update_db_url(&sconfig.db_url, &sconfig.db_password_path);
+    config.update_data_encryption_key(&sconfig.data_encryption_key);
+    config.update_slow_search_threshold(sconfig.slow_search_threshold_ms);
+    config.update_entry_cache_warmup(sconfig.entry_cache_warmup);
     config.update_role(sconfig.role);
+    config.update_write_origin(&sconfig.write_origin);
     config.update_output_mode(opt.commands.commonopt().output_mode.to_owned().into());
     config.update_trust_x_forward_for(sconfig.trust_x_forward_for);
     config.update_admin_bind_path(&sconfig.adminbindpath);
@@ -496,8 +846,26 @@ async fn start_daemon(
         KanidmdOpt::ShowReplicationCertificate { .. }
         | KanidmdOpt::RenewReplicationCertificate { .. }
         | KanidmdOpt::RefreshReplicationConsumer { .. }
+        | KanidmdOpt::ReplicationStatus { .. }
         | KanidmdOpt::RecoverAccount { .. }
-        | KanidmdOpt::HealthCheck(_) => (),
+        | KanidmdOpt::Schema { .. }
+        | KanidmdOpt::KeyObject { .. }
+        | KanidmdOpt::Maintenance { .. }
+        | KanidmdOpt::Config { .. }
+        | KanidmdOpt::SelfTest(_)
+        | KanidmdOpt::Doctor(_)
+        | KanidmdOpt::HealthCheck(_)
+        | KanidmdOpt::BreakGlassCodesGenerate(_)
+        | KanidmdOpt::PasswordBreachFilterBuild(_)
+        | KanidmdOpt::Database {
+            commands: DbCommands::Reindex(ReindexOpt { online: true, .. }),
+        }
+        | KanidmdOpt::Database {
+            commands: DbCommands::Reindex(ReindexOpt { status: true, .. }),
+        }
+        | KanidmdOpt::Database {
+            commands: DbCommands::Analyze(_),
+        } => (),
         _ => {
             // Okay - Lets now create our lock and go.
             #[allow(clippy::expect_used)]
@@ -529,7 +897,7 @@ async fn start_daemon(
         }
     }
 
-    kanidm_main(sconfig, config, opt).await
+    kanidm_main(sconfig, config, opt, log_level_handle).await
 }
 
 fn main() -> ExitCode {
@@ -565,21 +933,7 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE;
     }
 
-    let default_config_path = PathBuf::from(env!("KANIDM_SERVER_CONFIG_PATH"));
-
-    let maybe_config_path = if let Some(p) = opt.config_path() {
-        Some(p)
-    } else {
-        // The user didn't ask for a file, lets check if the default path exists?
-        if default_config_path.exists() {
-            // It does, lets use it.
-            Some(default_config_path)
-        } else {
-            // No default config, and no config specified, lets assume the user
-            // has selected environment variables.
-            None
-        }
-    };
+    let maybe_config_path = resolve_config_path(&opt);
 
     let sconfig = match ServerConfig::new(maybe_config_path) {
         Ok(c) => Some(c),
@@ -652,6 +1006,7 @@ async fn kanidm_main(
     sconfig: ServerConfig,
     mut config: Configuration,
     opt: KanidmdParser,
+    log_level_handle: LogLevelReloadHandle,
 ) -> ExitCode {
     match &opt.commands {
         KanidmdOpt::Server(_sopt) | KanidmdOpt::ConfigTest(_sopt) => {
@@ -665,86 +1020,8 @@ async fn kanidm_main(
             // configuration options that only relate to server mode
             config.update_config_for_server_mode(&sconfig);
 
-            if let Some(i_str) = &(sconfig.tls_chain) {
-                let i_path = PathBuf::from(i_str.as_str());
-                let i_meta = match metadata(&i_path) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        error!(
-                            "Unable to read metadata for TLS chain file '{}' - {:?}",
-                            &i_path.to_str().unwrap_or("invalid file path"),
-                            e
-                        );
-                        let diag = kanidm_lib_file_permissions::diagnose_path(&i_path);
-                        info!(%diag);
-                        return ExitCode::FAILURE;
-                    }
-                };
-                if !kanidm_lib_file_permissions::readonly(&i_meta) {
-                    warn!("permissions on {} may not be secure. Should be readonly to running uid. This could be a security risk ...", i_str);
-                }
-            }
-
-            if let Some(i_str) = &(sconfig.tls_key) {
-                let i_path = PathBuf::from(i_str.as_str());
-
-                let i_meta = match metadata(&i_path) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        error!(
-                            "Unable to read metadata for TLS key file '{}' - {:?}",
-                            &i_path.to_str().unwrap_or("invalid file path"),
-                            e
-                        );
-                        let diag = kanidm_lib_file_permissions::diagnose_path(&i_path);
-                        info!(%diag);
-                        return ExitCode::FAILURE;
-                    }
-                };
-                if !kanidm_lib_file_permissions::readonly(&i_meta) {
-                    warn!("permissions on {} may not be secure. Should be readonly to running uid. This could be a security risk ...", i_str);
-                }
-                #[cfg(not(target_os = "windows"))]
-                if i_meta.mode() & 0o007 != 0 {
-                    warn!("WARNING: {} has 'everyone' permission bits in the mode. This could be a security risk ...", i_str);
-                }
-            }
-
-            if let Some(ca_dir) = &(sconfig.tls_client_ca) {
-                // check that the TLS client CA config option is what we expect
-                let ca_dir_path = PathBuf::from(&ca_dir);
-                if !ca_dir_path.exists() {
-                    error!(
-                        "TLS CA folder {} does not exist, server startup will FAIL!",
-                        ca_dir
-                    );
-                    let diag = kanidm_lib_file_permissions::diagnose_path(&ca_dir_path);
-                    info!(%diag);
-                }
-
-                let i_meta = match metadata(&ca_dir_path) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        error!("Unable to read metadata for '{}' - {:?}", ca_dir, e);
-                        let diag = kanidm_lib_file_permissions::diagnose_path(&ca_dir_path);
-                        info!(%diag);
-                        return ExitCode::FAILURE;
-                    }
-                };
-                if !i_meta.is_dir() {
-                    error!(
-                        "ERROR: Refusing to run - TLS Client CA folder {} may not be a directory",
-                        ca_dir
-                    );
-                    return ExitCode::FAILURE;
-                }
-                if kanidm_lib_file_permissions::readonly(&i_meta) {
-                    warn!("WARNING: TLS Client CA folder permissions on {} indicate it may not be RW. This could cause the server start up to fail!", ca_dir);
-                }
-                #[cfg(not(target_os = "windows"))]
-                if i_meta.mode() & 0o007 != 0 {
-                    warn!("WARNING: TLS Client CA folder {} has 'everyone' permission bits in the mode. This could be a security risk ...", ca_dir);
-                }
+            if let Err(err) = check_tls_file_permissions(&sconfig) {
+                return err;
             }
 
             let sctx = create_server_core(config, config_test).await;
@@ -796,7 +1073,7 @@ async fn kanidm_main(
                                                     #[allow(clippy::unwrap_used)]
                                                     tokio::signal::unix::signal(sigterm).unwrap().recv().await
                                                 } => {
-                                                    // Reload TLS certificates
+                                                    // Reload the configuration and TLS certificates
                                                     // systemd has a special reload handler for this.
                                                     #[cfg(target_os = "linux")]
                                                     {
@@ -812,7 +1089,7 @@ async fn kanidm_main(
                                                     let _ = sd_notify::notify(true, &[sd_notify::NotifyState::Status("Reloading ...")]);
                                                     }
 
-                                                    sctx.tls_acceptor_reload().await;
+                                                    let _ = reload_config(&mut sctx, &opt, &log_level_handle).await;
 
                                                     // Systemd freaks out if you send the ready state too fast after the
                                                     // reload state and can kill Kanidmd as a result.
@@ -832,6 +1109,10 @@ async fn kanidm_main(
 
                                                     info!("Reload complete");
                                                 }
+                                                Some(request) = sctx.config_reload_requested() => {
+                                                    let result = reload_config(&mut sctx, &opt, &log_level_handle).await;
+                                                    let _ = request.respond.send(result);
+                                                }
                                                 Some(()) = async move {
                                                     let sigterm = tokio::signal::unix::SignalKind::user_defined1();
                                                     #[allow(clippy::unwrap_used)]
@@ -883,6 +1164,43 @@ async fn kanidm_main(
             config.update_config_for_server_mode(&sconfig);
             cert_generate_core(&config);
         }
+        KanidmdOpt::Config {
+            commands: ConfigCommands::Reload(copt),
+        } => {
+            info!("Running configuration reload ...");
+            let output_mode: ConsoleOutputMode = copt.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::ReloadConfig,
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::Config {
+            commands: ConfigCommands::Validate(_copt),
+        } => {
+            info!("Running in configuration validation mode ...");
+            config.update_config_for_server_mode(&sconfig);
+
+            if let Err(err) = check_tls_file_permissions(&sconfig) {
+                return err;
+            }
+
+            if let Err(err) = config_validate_core(&config) {
+                error!("Configuration is invalid - {}", err);
+                return ExitCode::FAILURE;
+            }
+
+            match sconfig.to_masked_string(config.output_mode) {
+                Ok(effective_config) => println!("{}", effective_config),
+                Err(err) => {
+                    error!("{}", err);
+                    return ExitCode::FAILURE;
+                }
+            }
+
+            info!("✅ Configuration is valid");
+        }
         KanidmdOpt::Database {
             commands: DbCommands::Backup(bopt),
         } => {
@@ -907,13 +1225,39 @@ async fn kanidm_main(
                     return ExitCode::FAILURE;
                 }
             };
-            restore_server_core(&config, p).await;
+            let base = match ropt.base.as_ref().map(|b| b.to_str()) {
+                Some(Some(b)) => Some(b),
+                Some(None) => {
+                    error!("Invalid base backup path");
+                    return ExitCode::FAILURE;
+                }
+                None => None,
+            };
+            let mode = match (ropt.entry.as_deref(), ropt.until.as_deref(), base) {
+                (Some(entry), _, _) => RestoreMode::Entry(entry),
+                (None, Some(until), Some(base_path)) => RestoreMode::Until { base_path, until },
+                _ => RestoreMode::Full,
+            };
+            restore_server_core(&config, p, mode).await;
         }
         KanidmdOpt::Database {
-            commands: DbCommands::Verify(_vopt),
+            commands: DbCommands::MigrateSqlite(mopt),
+        } => {
+            info!("Running in sqlite migration mode ...");
+            let p = match mopt.path.to_str() {
+                Some(p) => p,
+                None => {
+                    error!("Invalid sqlite database path");
+                    return ExitCode::FAILURE;
+                }
+            };
+            migrate_sqlite_server_core(&config, p).await;
+        }
+        KanidmdOpt::Database {
+            commands: DbCommands::Verify(vopt),
         } => {
             info!("Running in db verification mode ...");
-            verify_server_core(&config).await;
+            verify_server_core(&config, vopt.repair).await;
         }
         KanidmdOpt::ShowReplicationCertificate { commonopts } => {
             info!("Running show replication certificate ...");
@@ -952,24 +1296,62 @@ async fn kanidm_main(
                 .await;
             }
         }
-        KanidmdOpt::RecoverAccount { name, commonopts } => {
+        KanidmdOpt::ReplicationStatus { commonopts } => {
+            info!("Running replication status ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::ReplicationStatus,
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::RecoverAccount {
+            name,
+            tenant,
+            commonopts,
+        } => {
             info!("Running account recovery ...");
             let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
             submit_admin_req(
                 config.adminbindpath.as_str(),
                 AdminTaskRequest::RecoverAccount {
                     name: name.to_owned(),
+                    tenant: tenant.to_owned(),
                 },
                 output_mode,
             )
             .await;
         }
         KanidmdOpt::Database {
-            commands: DbCommands::Reindex(_copt),
+            commands: DbCommands::Reindex(ropt),
+        } if ropt.online || ropt.status => {
+            let output_mode: ConsoleOutputMode = ropt.commonopts.output_mode.to_owned().into();
+            let req = if ropt.online {
+                info!("Starting online reindex ...");
+                AdminTaskRequest::ReindexStart
+            } else {
+                AdminTaskRequest::ReindexStatus
+            };
+            submit_admin_req(config.adminbindpath.as_str(), req, output_mode).await;
+        }
+        KanidmdOpt::Database {
+            commands: DbCommands::Reindex(_ropt),
         } => {
             info!("Running in reindex mode ...");
             reindex_server_core(&config).await;
         }
+        KanidmdOpt::Database {
+            commands: DbCommands::Analyze(copt),
+        } => {
+            let output_mode: ConsoleOutputMode = copt.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::DatabaseAnalyze,
+                output_mode,
+            )
+            .await;
+        }
         KanidmdOpt::DbScan {
             commands: DbScanOpt::ListIndexes(_),
         } => {
@@ -1081,6 +1463,127 @@ async fn kanidm_main(
             .await;
         }
 
+        KanidmdOpt::Schema {
+            commands: SchemaCommands::Show(commonopts),
+        } => {
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::SchemaShow,
+                output_mode,
+            )
+            .await;
+        }
+
+        KanidmdOpt::Maintenance { commands } => {
+            let (commonopts, enabled) = match commands {
+                MaintenanceCommands::Enable(commonopts) => (commonopts, Some(true)),
+                MaintenanceCommands::Disable(commonopts) => (commonopts, Some(false)),
+                MaintenanceCommands::Status(commonopts) => (commonopts, None),
+            };
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::MaintenanceMode { enabled },
+                output_mode,
+            )
+            .await;
+        }
+
+        KanidmdOpt::KeyObject {
+            commands: KeyObjectCommands::List(commonopts),
+        } => {
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::KeyObjectList,
+                output_mode,
+            )
+            .await;
+        }
+
+        KanidmdOpt::KeyObject {
+            commands:
+                KeyObjectCommands::Rotate {
+                    key_object,
+                    commonopts,
+                },
+        } => {
+            info!("Running key object rotate ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::KeyObjectRotate {
+                    key_object: key_object.to_owned(),
+                },
+                output_mode,
+            )
+            .await;
+        }
+
+        KanidmdOpt::KeyObject {
+            commands:
+                KeyObjectCommands::Revoke {
+                    key_object,
+                    key_id,
+                    commonopts,
+                },
+        } => {
+            info!("Running key object revoke ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::KeyObjectRevoke {
+                    key_object: key_object.to_owned(),
+                    key_id: key_id.to_owned(),
+                },
+                output_mode,
+            )
+            .await;
+        }
+
+        KanidmdOpt::SelfTest(commonopts) => {
+            info!("Running self test ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::SelfTest,
+                output_mode,
+            )
+            .await;
+        }
+
+        KanidmdOpt::Doctor(commonopts) => {
+            info!("Running environment checks ...");
+            config.update_config_for_server_mode(&sconfig);
+
+            let report = doctor_core(&config, sconfig.config_files()).await;
+            let status = report.status();
+
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            match output_mode {
+                ConsoleOutputMode::JSON => {
+                    let json_output = serde_json::json!({
+                        "doctor": report
+                    });
+                    println!("{}", json_output);
+                }
+                ConsoleOutputMode::Text => {
+                    info!("doctor_status          : {}", status);
+                    for item in report.items {
+                        info!("------------------------");
+                        info!("check                  : {}", item.check);
+                        info!("status                 : {}", item.status);
+                        info!("detail                 : {}", item.detail);
+                    }
+                }
+            }
+
+            if status == SelfTestStatus::Fail {
+                return ExitCode::FAILURE;
+            }
+        }
+
         KanidmdOpt::Database {
             commands: DbCommands::Vacuum(_copt),
         } => {
@@ -1184,6 +1687,44 @@ async fn kanidm_main(
                 }
             }
         }
+        KanidmdOpt::BreakGlassCodesGenerate(bopt) => {
+            match break_glass_codes_generate_core(&bopt.account, &bopt.output, bopt.count) {
+                Ok(codes) => {
+                    info!(
+                        "Generated {} recovery codes for {} in {}",
+                        codes.len(),
+                        bopt.account,
+                        bopt.output.display()
+                    );
+                    println!("Store these codes offline. They will not be displayed again.");
+                    for code in codes {
+                        println!("{}", code);
+                    }
+                }
+                Err(err) => {
+                    error!("Unable to generate break glass codes - {}", err);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        KanidmdOpt::PasswordBreachFilterBuild(bopt) => {
+            info!("Building password breach filter ...");
+            match password_breach_filter_build_core(
+                &bopt.input,
+                &bopt.output,
+                bopt.false_positive_rate,
+            ) {
+                Ok(items) => info!(
+                    "Added {} breached password hashes to {}",
+                    items,
+                    bopt.output.display()
+                ),
+                Err(err) => {
+                    error!("Unable to build password breach filter - {}", err);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
         KanidmdOpt::Version(_) => {}
     }
     ExitCode::SUCCESS
@@ -17,11 +17,89 @@ struct BackupOpt {
     commonopts: CommonOpt,
 }
 
+#[derive(Debug, Args)]
+struct MigrateSqliteOpt {
+    #[clap(value_parser)]
+    /// The path of the SQLite database to copy into the PostgreSQL database set by db_url.
+    path: PathBuf,
+    #[clap(flatten)]
+    commonopts: CommonOpt,
+}
+
+#[derive(Debug, Args)]
+struct ReindexOpt {
+    /// Rebuild the indexes of the running server one attribute at a time, rather than
+    /// reindexing offline.
+    #[clap(long, conflicts_with = "status")]
+    online: bool,
+    /// Show the progress of an online reindex.
+    #[clap(long)]
+    status: bool,
+    #[clap(flatten)]
+    commonopts: CommonOpt,
+}
+
+#[derive(Debug, Args)]
+struct VerifyOpt {
+    /// Apply the safe automated fixes for any issues found, such as rebuilding the indexes,
+    /// removing references to missing entries and recalculating memberof. The server must be
+    /// stopped.
+    #[clap(long)]
+    repair: bool,
+    #[clap(flatten)]
+    commonopts: CommonOpt,
+}
+
+#[derive(Debug, Args)]
+struct BreakGlassCodesGenerateOpt {
+    #[clap(value_parser)]
+    /// The name of the emergency account. The account must exist for the codes to be used.
+    account: String,
+    #[clap(value_parser)]
+    /// Write the hashes of the codes to this path, which is loaded by break_glass_codes. Any
+    /// other accounts in an existing file are kept.
+    output: PathBuf,
+    /// How many codes to generate. Any existing codes of the account are replaced.
+    #[clap(long, default_value_t = 8)]
+    count: usize,
+    #[clap(flatten)]
+    commonopts: CommonOpt,
+}
+
+#[derive(Debug, Args)]
+struct PasswordBreachFilterBuildOpt {
+    #[clap(value_parser)]
+    /// The dataset of breached password SHA-1 hashes, one per line in hex, optionally
+    /// followed by ":" and a count. This is the format of the Have I Been Pwned dataset.
+    input: PathBuf,
+    #[clap(value_parser)]
+    /// Write the filter to this path.
+    output: PathBuf,
+    /// The rate of false positives, where a password that was never breached is rejected.
+    /// Lower rates require a larger filter.
+    #[clap(long, default_value_t = 0.001)]
+    false_positive_rate: f64,
+    #[clap(flatten)]
+    commonopts: CommonOpt,
+}
+
 #[derive(Debug, Args)]
 struct RestoreOpt {
     #[clap(value_parser)]
     /// Restore from this path. Should be created with "backup".
     path: PathBuf,
+    /// Restore the database to its state at this time (RFC3339), by replaying the changes
+    /// in `path` over an earlier base backup.
+    #[clap(long, requires = "base")]
+    until: Option<String>,
+    /// The base backup for a point in time restore. This must have been taken before the
+    /// time given by `--until`.
+    #[clap(long, requires = "until")]
+    base: Option<PathBuf>,
+    /// Only restore this entry (by name or uuid), and any deleted entries that it references.
+    /// The rest of the database is left unchanged.
+    #[clap(long, conflicts_with_all = ["until", "base"])]
+    entry: Option<String>,
     #[clap(flatten)]
     commonopts: CommonOpt,
 }
@@ -66,6 +144,68 @@ enum DomainSettingsCmds {
     },
 }
 
+#[derive(Debug, Subcommand)]
+enum SchemaCommands {
+    #[clap(name = "show")]
+    /// Show the attributes and classes of the schema that is loaded by the running server
+    Show(CommonOpt),
+}
+
+#[derive(Debug, Subcommand)]
+enum MaintenanceCommands {
+    #[clap(name = "enable")]
+    /// Make the running server read only. Changes from clients are refused, but reads and
+    /// authentication continue.
+    Enable(CommonOpt),
+    #[clap(name = "disable")]
+    /// Allow changes from clients again
+    Disable(CommonOpt),
+    #[clap(name = "status")]
+    /// Show if the running server is in maintenance mode
+    Status(CommonOpt),
+}
+
+#[derive(Debug, Subcommand)]
+enum KeyObjectCommands {
+    #[clap(name = "list")]
+    /// List the key objects of the domain, such as the domain itself and oauth2 clients, and
+    /// the status of their keys
+    List(CommonOpt),
+    #[clap(name = "rotate")]
+    /// Rotate the keys of a key object. New signatures use the new keys immediately, and the
+    /// former keys remain valid for verification until they are revoked.
+    Rotate {
+        /// The name or uuid of the key object.
+        key_object: String,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+    #[clap(name = "revoke")]
+    /// Revoke a key of a key object. Anything signed by this key is no longer valid.
+    Revoke {
+        /// The name or uuid of the key object.
+        key_object: String,
+        /// The id of the key to revoke, as shown by "list".
+        key_id: String,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    #[clap(name = "validate")]
+    /// Validate the server configuration, including any included files and environment
+    /// variable overrides, and display the effective configuration. This does not open the
+    /// database or start the server.
+    Validate(CommonOpt),
+    #[clap(name = "reload")]
+    /// Reload the configuration of the running server. The log level, trust_x_forward_for,
+    /// online backups and the TLS certificate are applied without a restart. This is also
+    /// triggered by sending SIGHUP to the server.
+    Reload(CommonOpt),
+}
+
 #[derive(Debug, Subcommand)]
 enum DbCommands {
     #[clap(name = "vacuum")]
@@ -77,12 +217,18 @@ enum DbCommands {
     #[clap(name = "restore")]
     /// Restore the database content (offline)
     Restore(RestoreOpt),
+    #[clap(name = "migrate-sqlite")]
+    /// Copy the content of an SQLite database into the configured PostgreSQL database (offline)
+    MigrateSqlite(MigrateSqliteOpt),
     #[clap(name = "verify")]
-    /// Verify database and entity consistency.
-    Verify(CommonOpt),
+    /// Verify database and entity consistency, optionally repairing what can be safely fixed.
+    Verify(VerifyOpt),
     #[clap(name = "reindex")]
-    /// Reindex the database (offline)
-    Reindex(CommonOpt),
+    /// Reindex the database (offline, or online with --online)
+    Reindex(ReindexOpt),
+    #[clap(name = "analyze")]
+    /// Show statistics of the searches on the running server, and recommend missing indexes
+    Analyze(CommonOpt),
 }
 
 #[derive(Debug, Args)]
@@ -170,6 +316,10 @@ impl KanidmdParser {
         match self.commands {
             KanidmdOpt::Server(ref c) => c.config_path.clone(),
             KanidmdOpt::ConfigTest(ref c) => c.config_path.clone(),
+            KanidmdOpt::Config { ref commands } => match commands {
+                ConfigCommands::Validate(ref c) => c.config_path.clone(),
+                ConfigCommands::Reload(ref c) => c.config_path.clone(),
+            },
             KanidmdOpt::CertGenerate(ref c) => c.config_path.clone(),
             KanidmdOpt::RecoverAccount { ref commonopts, .. } => commonopts.config_path.clone(),
             KanidmdOpt::ShowReplicationCertificate { ref commonopts, .. } => {
@@ -181,6 +331,7 @@ impl KanidmdParser {
             KanidmdOpt::RefreshReplicationConsumer { ref commonopts, .. } => {
                 commonopts.config_path.clone()
             }
+            KanidmdOpt::ReplicationStatus { ref commonopts } => commonopts.config_path.clone(),
             KanidmdOpt::DbScan { ref commands } => match commands {
                 DbScanOpt::ListIndexes(ref c) => c.config_path.clone(),
                 DbScanOpt::ListIndex(ref c) => c.commonopts.config_path.clone(),
@@ -199,8 +350,10 @@ impl KanidmdParser {
                 DbCommands::Vacuum(ref c) => c.config_path.clone(),
                 DbCommands::Backup(ref c) => c.commonopts.config_path.clone(),
                 DbCommands::Restore(ref c) => c.commonopts.config_path.clone(),
-                DbCommands::Verify(ref c) => c.config_path.clone(),
-                DbCommands::Reindex(ref c) => c.config_path.clone(),
+                DbCommands::MigrateSqlite(ref c) => c.commonopts.config_path.clone(),
+                DbCommands::Verify(ref c) => c.commonopts.config_path.clone(),
+                DbCommands::Reindex(ref c) => c.commonopts.config_path.clone(),
+                DbCommands::Analyze(ref c) => c.config_path.clone(),
             },
             KanidmdOpt::DomainSettings { ref commands } => match commands {
                 DomainSettingsCmds::Show { ref commonopts } => commonopts.config_path.clone(),
@@ -211,6 +364,21 @@ impl KanidmdParser {
                     commonopts.config_path.clone()
                 }
             },
+            KanidmdOpt::Schema { ref commands } => match commands {
+                SchemaCommands::Show(ref c) => c.config_path.clone(),
+            },
+            KanidmdOpt::KeyObject { ref commands } => match commands {
+                KeyObjectCommands::List(ref c) => c.config_path.clone(),
+                KeyObjectCommands::Rotate { ref commonopts, .. } => commonopts.config_path.clone(),
+                KeyObjectCommands::Revoke { ref commonopts, .. } => commonopts.config_path.clone(),
+            },
+            KanidmdOpt::Maintenance { ref commands } => match commands {
+                MaintenanceCommands::Enable(ref c)
+                | MaintenanceCommands::Disable(ref c)
+                | MaintenanceCommands::Status(ref c) => c.config_path.clone(),
+            },
+            KanidmdOpt::SelfTest(ref c) => c.config_path.clone(),
+            KanidmdOpt::Doctor(ref c) => c.config_path.clone(),
             KanidmdOpt::HealthCheck(ref c) => c.commonopts.config_path.clone(),
             KanidmdOpt::Version(ref c) => c.config_path.clone(),
         }
@@ -225,6 +393,12 @@ enum KanidmdOpt {
     #[clap(name = "configtest")]
     /// Test the IDM Server configuration, without starting network listeners.
     ConfigTest(CommonOpt),
+    /// Server configuration tools.
+    #[clap(name = "config")]
+    Config {
+        #[clap(subcommand)]
+        commands: ConfigCommands,
+    },
     #[clap(name = "cert-generate")]
     /// Create a self-signed ca and tls certificate in the locations listed from the
     /// configuration. These certificates should *not* be used in production, they
@@ -236,6 +410,10 @@ enum KanidmdOpt {
         #[clap(value_parser)]
         /// The account name to recover credentials for.
         name: String,
+        #[clap(long)]
+        /// The domain of the tenant that the account belongs to. If unset, the account is of
+        /// the primary domain of this server.
+        tenant: Option<String>,
         #[clap(flatten)]
         commonopts: CommonOpt,
     },
@@ -258,6 +436,11 @@ enum KanidmdOpt {
         #[clap(long = "i-want-to-refresh-this-servers-database")]
         proceed: bool,
     },
+    /// Display the range of changes this server holds from each replication partner
+    ReplicationStatus {
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
     // #[clap(name = "reset_server_id")]
     // ResetServerId(CommonOpt),
     #[clap(name = "db-scan")]
@@ -278,6 +461,45 @@ enum KanidmdOpt {
         #[clap(subcommand)]
         commands: DomainSettingsCmds,
     },
+    /// Inspect the schema of the running server
+    #[clap(name = "schema")]
+    Schema {
+        #[clap(subcommand)]
+        commands: SchemaCommands,
+    },
+    /// Inspect and manage the cryptographic keys of the running server
+    #[clap(name = "key-object")]
+    KeyObject {
+        #[clap(subcommand)]
+        commands: KeyObjectCommands,
+    },
+    /// Make the running server read only during backups, migrations and upgrades
+    #[clap(name = "maintenance")]
+    Maintenance {
+        #[clap(subcommand)]
+        commands: MaintenanceCommands,
+    },
+
+    /// Run the self tests of the running server, checking key material, password hashing
+    /// cost and clock skew against replication partners.
+    #[clap(name = "self-test")]
+    SelfTest(CommonOpt),
+
+    /// Check the environment of the server, such as file permissions, the TLS chain and names,
+    /// clock synchronisation, database integrity and the reachability of replication partners.
+    #[clap(name = "doctor")]
+    Doctor(CommonOpt),
+
+    /// Generate single use recovery codes for an emergency account, that is loaded by
+    /// break_glass_codes. The codes are displayed once. This does not need the server to be
+    /// running.
+    #[clap(name = "break-glass-codes-generate")]
+    BreakGlassCodesGenerate(BreakGlassCodesGenerateOpt),
+
+    /// Build the bloom filter of breached passwords that is loaded by password_breach_filter.
+    /// This does not need the server to be running.
+    #[clap(name = "password-breach-filter-build")]
+    PasswordBreachFilterBuild(PasswordBreachFilterBuildOpt),
 
     /// Load the server config and check services are listening
     #[clap(name = "healthcheck")]
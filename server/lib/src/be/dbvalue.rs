@@ -67,6 +67,9 @@ pub enum DbValueIntentTokenStateV1 {
         unixcred_can_edit: bool,
         #[serde(default)]
         sshpubkey_can_edit: bool,
+        // Tokens created before multi-use intents were single use.
+        #[serde(default)]
+        uses_remaining: Option<u32>,
     },
     #[serde(rename = "p")]
     InProgress {
@@ -85,6 +88,9 @@ pub enum DbValueIntentTokenStateV1 {
         unixcred_can_edit: bool,
         #[serde(default)]
         sshpubkey_can_edit: bool,
+        // Tokens created before multi-use intents were single use.
+        #[serde(default)]
+        uses_remaining: Option<u32>,
     },
     #[serde(rename = "c")]
     Consumed { max_ttl: Duration },
@@ -539,6 +545,14 @@ pub enum DbValueAuthTypeV1 {
     Passkey,
     #[serde(rename = "ap")]
     AttestedPasskey,
+    #[serde(rename = "el")]
+    EmailLink,
+    #[serde(rename = "fd")]
+    Federated,
+    #[serde(rename = "ja")]
+    JwtAssertion,
+    #[serde(rename = "im")]
+    Impersonation,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -635,9 +649,21 @@ pub enum DbValueApiToken {
         issued_by: DbValueIdentityId,
         #[serde(rename = "s", default)]
         scope: DbValueApiTokenScopeV1,
+        #[serde(rename = "p", default)]
+        permissions: Vec<DbValueApiTokenPermissionV1>,
     },
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct DbValueApiTokenPermissionV1 {
+    #[serde(rename = "c")]
+    pub class: String,
+    #[serde(rename = "n")]
+    pub name_prefix: Option<String>,
+    #[serde(rename = "a")]
+    pub attrs: Vec<String>,
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum DbValueOauth2Session {
@@ -676,9 +702,17 @@ pub enum DbValueOauth2Session {
         issued_at: String,
         #[serde(rename = "r")]
         rs_uuid: Uuid,
+        #[serde(rename = "x", default)]
+        revoke_reason: Option<DbValueOauth2SessionRevokeReasonV1>,
     },
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DbValueOauth2SessionRevokeReasonV1 {
+    #[serde(rename = "r")]
+    RefreshTokenReuse,
+}
+
 // Internal representation of an image
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum DbValueImage {
@@ -693,6 +727,11 @@ pub enum DbValueImage {
 pub enum DbValueKeyUsage {
     JwsEs256,
     JweA128GCM,
+    ReplTrustAnchor,
+    SshCa,
+    Saml2Signing,
+    Kerberos,
+    JwsRs256,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -733,6 +772,60 @@ pub enum DbValueApplicationPassword {
     },
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum DbValueCredentialFactorV1 {
+    #[serde(rename = "pw")]
+    Password,
+    #[serde(rename = "to")]
+    Totp(String),
+    #[serde(rename = "sk")]
+    SecurityKey,
+    #[serde(rename = "bc")]
+    BackupCode,
+    #[serde(rename = "pk")]
+    Passkey,
+    #[serde(rename = "at")]
+    ApiToken,
+    #[serde(rename = "se")]
+    Session,
+    #[serde(rename = "el")]
+    EmailLink,
+    #[serde(rename = "fd")]
+    Federated,
+    #[serde(rename = "ja")]
+    JwtAssertion(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum DbValueCredentialUsage {
+    V1 {
+        #[serde(rename = "u")]
+        refer: Uuid,
+        #[serde(rename = "f")]
+        factor: DbValueCredentialFactorV1,
+        #[serde(rename = "l")]
+        last_used: String,
+        #[serde(rename = "c")]
+        use_count: u64,
+        #[serde(rename = "s")]
+        source: Option<String>,
+        #[serde(rename = "a", default)]
+        user_agent: Option<String>,
+    },
+}
+
+/// A value set that is encrypted at rest. The ciphertext is the serialised value set, sealed
+/// with the data encryption key `key_id`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DbValueEncrypted {
+    #[serde(rename = "k")]
+    pub key_id: Uuid,
+    #[serde(rename = "n")]
+    pub nonce: Vec<u8>,
+    #[serde(rename = "c")]
+    pub ciphertext: Vec<u8>,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum DbValueSetV2 {
     #[serde(rename = "U8")]
@@ -829,6 +922,10 @@ pub enum DbValueSetV2 {
     Certificate(Vec<DbValueCertificate>),
     #[serde(rename = "AP")]
     ApplicationPassword(Vec<DbValueApplicationPassword>),
+    #[serde(rename = "CU")]
+    CredentialUsage(Vec<DbValueCredentialUsage>),
+    #[serde(rename = "EV")]
+    Encrypted(DbValueEncrypted),
 }
 
 impl DbValueSetV2 {
@@ -882,6 +979,9 @@ impl DbValueSetV2 {
             DbValueSetV2::KeyInternal(set) => set.len(),
             DbValueSetV2::Certificate(set) => set.len(),
             DbValueSetV2::ApplicationPassword(set) => set.len(),
+            DbValueSetV2::CredentialUsage(set) => set.len(),
+            // The number of values is only known once decrypted.
+            DbValueSetV2::Encrypted(_) => 1,
         }
     }
 
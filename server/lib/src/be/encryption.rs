@@ -0,0 +1,419 @@
+//! Encryption at rest of sensitive values. Value sets that hold secrets, such as credentials,
+//! totp secrets and radius secrets, are encrypted with a data encryption key before they are
+//! written to the database, so that a copy of the database file alone does not reveal them.
+//!
+//! The data encryption keys are stored in the database wrapped by a key encryption key. The key
+//! encryption key is never stored in the database, and must be provided to the backend from
+//! outside, such as from a file that is only readable by the server.
+
+use std::collections::BTreeMap;
+
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use uuid::Uuid;
+
+use crate::be::dbentry::{DbEntry, DbEntryVers};
+use crate::be::dbvalue::{DbValueEncrypted, DbValueSetV2};
+use crate::be::idl_arc_sqlite::IdlArcSqliteTransaction;
+use crate::be::idl_storage::IdlStorageTransaction;
+use crate::be::keystorage::{KeyHandle, KeyHandleId};
+use crate::be::{Backend, BackendWriteTransaction, IdList};
+use crate::prelude::*;
+
+const DATA_KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// The key that wraps the data encryption keys of the database.
+pub type KeyEncryptionKey = [u8; DATA_KEY_LEN];
+
+type DataKey = [u8; DATA_KEY_LEN];
+
+fn seal(key: &DataKey, aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), OperationError> {
+    let mut nonce = [0; NONCE_LEN];
+    let mut tag = [0; TAG_LEN];
+    let mut ciphertext = rand_bytes(&mut nonce)
+        .and_then(|()| {
+            encrypt_aead(
+                Cipher::aes_256_gcm(),
+                key,
+                Some(&nonce),
+                aad,
+                plaintext,
+                &mut tag,
+            )
+        })
+        .map_err(|err| {
+            error!(?err, "Unable to encrypt value");
+            OperationError::CryptographyError
+        })?;
+
+    ciphertext.extend_from_slice(&tag);
+    Ok((nonce.to_vec(), ciphertext))
+}
+
+fn unseal(key: &DataKey, aad: &[u8], nonce: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+    if nonce.len() != NONCE_LEN || sealed.len() < TAG_LEN {
+        return None;
+    }
+
+    let (ciphertext, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+
+    decrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(nonce),
+        aad,
+        ciphertext,
+        tag,
+    )
+    .map_err(|err| {
+        debug!(?err, "Unable to decrypt value");
+    })
+    .ok()
+}
+
+/// Whether the values of this value set are encrypted at rest.
+fn is_sensitive(vs: &DbValueSetV2) -> bool {
+    matches!(
+        vs,
+        DbValueSetV2::Credential(_)
+            | DbValueSetV2::SecretValue(_)
+            | DbValueSetV2::TotpSecret(_)
+            | DbValueSetV2::ApplicationPassword(_)
+            | DbValueSetV2::KeyInternal(_)
+            | DbValueSetV2::EcKeyPrivate(_)
+    )
+}
+
+/// Encrypted values are bound to the entry and attribute they belong to, so that they can't
+/// be moved to another entry or attribute in the database.
+fn value_aad(entry_uuid: Uuid, attr: &Attribute) -> Vec<u8> {
+    let mut aad = entry_uuid.as_bytes().to_vec();
+    aad.extend_from_slice(attr.as_str().as_bytes());
+    aad
+}
+
+fn dbentry_uuid(attrs: &BTreeMap<Attribute, DbValueSetV2>) -> Result<Uuid, OperationError> {
+    let entry_uuid = match attrs.get(&Attribute::Uuid) {
+        Some(DbValueSetV2::Uuid(uuids)) => uuids.first().copied(),
+        _ => None,
+    };
+
+    entry_uuid.ok_or_else(|| {
+        error!("Unable to determine the uuid of an entry to encrypt or decrypt its values");
+        OperationError::InvalidEntryState
+    })
+}
+
+pub(crate) struct DataEncryptionKeys {
+    /// The key that new values are encrypted with. Older keys are retained to decrypt values
+    /// that were written before the active key changed.
+    active: Uuid,
+    keys: BTreeMap<Uuid, DataKey>,
+}
+
+impl DataEncryptionKeys {
+    pub(crate) fn generate() -> Result<Self, OperationError> {
+        let mut key = [0; DATA_KEY_LEN];
+        rand_bytes(&mut key).map_err(|err| {
+            error!(?err, "Unable to generate data encryption key");
+            OperationError::CryptographyError
+        })?;
+
+        let active = Uuid::new_v4();
+        Ok(DataEncryptionKeys {
+            active,
+            keys: BTreeMap::from([(active, key)]),
+        })
+    }
+
+    /// Wrap these keys with the key encryption key so that they can be stored.
+    pub(crate) fn wrap(&self, kek: &KeyEncryptionKey) -> Result<KeyHandle, OperationError> {
+        let wrapped = self
+            .keys
+            .iter()
+            .map(|(key_id, key)| {
+                seal(kek, key_id.as_bytes(), key).map(|(mut nonce, ciphertext)| {
+                    nonce.extend_from_slice(&ciphertext);
+                    (*key_id, nonce)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(KeyHandle::DataEncryptionKeys {
+            active: self.active,
+            wrapped,
+        })
+    }
+
+    pub(crate) fn unwrap(
+        handle: &KeyHandle,
+        kek: &KeyEncryptionKey,
+    ) -> Result<Self, OperationError> {
+        let KeyHandle::DataEncryptionKeys { active, wrapped } = handle else {
+            error!("Key handle does not contain data encryption keys");
+            return Err(OperationError::InvalidDbState);
+        };
+
+        let keys = wrapped
+            .iter()
+            .map(|(key_id, sealed)| {
+                if sealed.len() < NONCE_LEN {
+                    return None;
+                }
+                let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+                unseal(kek, key_id.as_bytes(), nonce, ciphertext)
+                    .and_then(|key| DataKey::try_from(key).ok())
+                    .map(|key| (*key_id, key))
+            })
+            .collect::<Option<BTreeMap<_, _>>>()
+            .filter(|keys| keys.contains_key(active))
+            .ok_or_else(|| {
+                error!("Unable to unwrap the data encryption keys, the key encryption key may be incorrect");
+                OperationError::DB0009DataEncryptionKeyInvalid
+            })?;
+
+        Ok(DataEncryptionKeys {
+            active: *active,
+            keys,
+        })
+    }
+
+    fn encrypt_valueset(
+        &self,
+        aad: &[u8],
+        vs: &DbValueSetV2,
+    ) -> Result<DbValueSetV2, OperationError> {
+        let key = self.keys.get(&self.active).ok_or_else(|| {
+            error!("The active data encryption key is missing");
+            OperationError::InvalidState
+        })?;
+
+        let plaintext = serde_json::to_vec(vs).map_err(|err| {
+            error!(?err, "Unable to serialise value for encryption");
+            OperationError::SerdeJsonError
+        })?;
+
+        seal(key, aad, &plaintext).map(|(nonce, ciphertext)| {
+            DbValueSetV2::Encrypted(DbValueEncrypted {
+                key_id: self.active,
+                nonce,
+                ciphertext,
+            })
+        })
+    }
+
+    fn decrypt_valueset(
+        &self,
+        aad: &[u8],
+        encrypted: &DbValueEncrypted,
+    ) -> Result<DbValueSetV2, OperationError> {
+        self.keys
+            .get(&encrypted.key_id)
+            .and_then(|key| unseal(key, aad, &encrypted.nonce, &encrypted.ciphertext))
+            .and_then(|plaintext| serde_json::from_slice(&plaintext).ok())
+            .ok_or_else(|| {
+                error!(key_id = %encrypted.key_id, "Unable to decrypt value");
+                OperationError::DB0010EncryptedValueInvalid
+            })
+    }
+
+    /// Encrypt the sensitive values of this entry before it is written to the database.
+    pub(crate) fn seal_dbentry(&self, dbe: &mut DbEntry) -> Result<(), OperationError> {
+        let DbEntryVers::V3 { attrs, .. } = &mut dbe.ent;
+        let entry_uuid = dbentry_uuid(attrs)?;
+
+        for (attr, vs) in attrs.iter_mut().filter(|(_, vs)| is_sensitive(vs)) {
+            *vs = self.encrypt_valueset(&value_aad(entry_uuid, attr), vs)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decrypt the values of this entry, if it has any encrypted values. Values that were written
+/// before encryption was enabled are left as is. This fails if there are encrypted values but
+/// no data encryption keys.
+pub(crate) fn unseal_dbentry(
+    keys: Option<&DataEncryptionKeys>,
+    dbe: &mut DbEntry,
+) -> Result<(), OperationError> {
+    let DbEntryVers::V3 { attrs, .. } = &mut dbe.ent;
+
+    if !attrs
+        .values()
+        .any(|vs| matches!(vs, DbValueSetV2::Encrypted(_)))
+    {
+        return Ok(());
+    }
+
+    let keys = keys.ok_or_else(|| {
+        error!("Entry has encrypted values, but no data encryption key is configured");
+        OperationError::DB0008DataEncryptionKeyRequired
+    })?;
+
+    let entry_uuid = dbentry_uuid(attrs)?;
+
+    for (attr, vs) in attrs.iter_mut() {
+        if let DbValueSetV2::Encrypted(encrypted) = vs {
+            *vs = keys.decrypt_valueset(&value_aad(entry_uuid, attr), encrypted)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl Backend {
+    /// Load the data encryption keys of the database, or generate them if encryption at rest
+    /// has just been enabled. When the keys are generated, all entries are rewritten so that
+    /// their existing sensitive values are encrypted.
+    pub(super) fn setup_data_encryption(&self) -> Result<(), OperationError> {
+        let mut idl_write = self.idlayer.write()?;
+        let handle = idl_write.get_key_handle(KeyHandleId::DataEncryptionKeys)?;
+
+        let Some(kek) = self.cfg.data_encryption_key.as_ref() else {
+            if handle.is_some() {
+                error!("The database has data encryption keys, but no data encryption key is configured");
+                return Err(OperationError::DB0008DataEncryptionKeyRequired);
+            }
+            return Ok(());
+        };
+
+        match handle {
+            Some(handle) => {
+                let data_keys = DataEncryptionKeys::unwrap(&handle, kek)?;
+                self.idlayer.set_data_keys(data_keys)
+            }
+            None => {
+                info!("Enabling encryption of sensitive values at rest");
+                let data_keys = DataEncryptionKeys::generate()?;
+                idl_write.set_key_handle(KeyHandleId::DataEncryptionKeys, data_keys.wrap(kek)?)?;
+                self.idlayer.set_data_keys(data_keys)?;
+
+                let entries = idl_write.get_identry(&IdList::AllIds)?;
+                idl_write.write_identries(entries.iter().map(|e| e.as_ref()))?;
+                idl_write.commit()
+            }
+        }
+    }
+}
+
+impl BackendWriteTransaction<'_> {
+    /// Decrypt the values of an entry that was read from outside of the database, such as from
+    /// a backup.
+    pub(crate) fn unseal_dbentry(&self, dbe: &mut DbEntry) -> Result<(), OperationError> {
+        unseal_dbentry(self.idlayer.db.get_data_keys(), dbe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{unseal_dbentry, DataEncryptionKeys};
+    use crate::be::dbentry::{DbEntry, DbEntryVers};
+    use crate::be::dbvalue::DbValueSetV2;
+    use crate::be::idl_arc_sqlite::IdlArcSqliteTransaction;
+    use crate::be::{Backend, BackendConfig, BackendTransaction, IdList};
+    use crate::entry::{Entry, EntryInit, EntryNew};
+    use crate::prelude::*;
+
+    #[test]
+    fn test_be_encryption_seal_unseal() {
+        let kek = [7; 32];
+        let keys = DataEncryptionKeys::generate().expect("Failed to generate keys");
+
+        let entry_uuid = Uuid::new_v4();
+        let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+        e.add_ava(Attribute::Uuid, Value::Uuid(entry_uuid));
+        e.add_ava(
+            Attribute::RadiusSecret,
+            Value::new_secret_str("very secret"),
+        );
+        let mut dbe = e.into_sealed_committed().to_dbentry();
+
+        keys.seal_dbentry(&mut dbe).expect("Failed to seal entry");
+
+        let serialised = serde_json::to_string(&dbe).expect("Failed to serialise entry");
+        assert!(!serialised.contains("very secret"));
+
+        let DbEntryVers::V3 { attrs, .. } = &dbe.ent;
+        assert!(matches!(
+            attrs.get(&Attribute::RadiusSecret),
+            Some(DbValueSetV2::Encrypted(_))
+        ));
+        // Values that aren't sensitive are left alone.
+        assert!(matches!(
+            attrs.get(&Attribute::Uuid),
+            Some(DbValueSetV2::Uuid(_))
+        ));
+
+        // Without keys the entry can't be loaded.
+        let mut no_keys: DbEntry = serde_json::from_str(&serialised).unwrap();
+        assert_eq!(
+            unseal_dbentry(None, &mut no_keys),
+            Err(OperationError::DB0008DataEncryptionKeyRequired)
+        );
+
+        // The keys survive being wrapped and unwrapped, but only with the same kek.
+        let handle = keys.wrap(&kek).expect("Failed to wrap keys");
+        assert_eq!(
+            DataEncryptionKeys::unwrap(&handle, &[8; 32]).err(),
+            Some(OperationError::DB0009DataEncryptionKeyInvalid)
+        );
+        let keys = DataEncryptionKeys::unwrap(&handle, &kek).expect("Failed to unwrap keys");
+
+        unseal_dbentry(Some(&keys), &mut dbe).expect("Failed to unseal entry");
+        let DbEntryVers::V3 { attrs, .. } = &dbe.ent;
+        assert_eq!(
+            attrs.get(&Attribute::RadiusSecret),
+            Some(&DbValueSetV2::SecretValue(vec!["very secret".to_string()]))
+        );
+
+        // An encrypted value moved to another entry can't be decrypted.
+        let mut moved: DbEntry = serde_json::from_str(
+            &serialised.replace(&entry_uuid.to_string(), &Uuid::new_v4().to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            unseal_dbentry(Some(&keys), &mut moved),
+            Err(OperationError::DB0010EncryptedValueInvalid)
+        );
+    }
+
+    #[test]
+    fn test_be_encryption_at_rest() {
+        sketching::test_init();
+        let mut cfg = BackendConfig::new_test("main");
+        cfg.set_data_encryption_key([7; 32]);
+        let be = Backend::new(cfg, Vec::new(), false).expect("Failed to setup backend");
+
+        let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+        e.add_ava(Attribute::Uuid, Value::Uuid(Uuid::new_v4()));
+        e.add_ava(
+            Attribute::RadiusSecret,
+            Value::new_secret_str("very secret"),
+        );
+
+        let mut be_write = be.write().expect("Failed to begin write");
+        be_write
+            .create(&Cid::new_zero(), vec![e.into_sealed_new()])
+            .expect("Failed to create entry");
+        be_write.commit().expect("Failed to commit");
+
+        let mut be_read = be.read().expect("Failed to begin read");
+        // The value is encrypted in the database ...
+        let raw = be_read.list_id2entry().expect("Failed to list entries");
+        assert_eq!(raw.len(), 1);
+        assert!(!raw[0].1.contains("very secret"));
+
+        // ... but not once it is loaded.
+        let entries = be_read
+            .get_idlayer()
+            .get_identry(&IdList::AllIds)
+            .expect("Failed to load entries");
+        assert_eq!(
+            entries[0].get_ava_single_secret(Attribute::RadiusSecret),
+            Some("very secret")
+        );
+    }
+}
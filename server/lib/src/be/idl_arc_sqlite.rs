@@ -5,6 +5,7 @@ use std::ops::DerefMut;
 use std::sync::Arc;
 use std::time::Duration;
 
+use concread::arcache::stats::ARCacheWriteStat;
 use concread::arcache::{ARCache, ARCacheBuilder, ARCacheReadTxn, ARCacheWriteTxn};
 use concread::cowcell::*;
 use hashbrown::HashMap;
@@ -14,16 +15,18 @@ use kanidm_proto::internal::{ConsistencyError, OperationError};
 use tracing::trace;
 use uuid::Uuid;
 
-use crate::be::idl_sqlite::{
-    IdlSqlite, IdlSqliteReadTransaction, IdlSqliteTransaction, IdlSqliteWriteTransaction,
+use crate::be::encryption::DataEncryptionKeys;
+use crate::be::idl_storage::{
+    IdlStorage, IdlStorageRead, IdlStorageTransaction, IdlStorageWrite, IdlStorageWriteTransaction,
 };
 use crate::be::idxkey::{
     IdlCacheKey, IdlCacheKeyRef, IdlCacheKeyToRef, IdxKey, IdxKeyRef, IdxKeyToRef, IdxNameKey,
     IdxSlope,
 };
 use crate::be::keystorage::{KeyHandle, KeyHandleId};
-use crate::be::{BackendConfig, IdList, IdRawEntry};
+use crate::be::{BackendConfig, DbCompaction, IdList, IdRawEntry};
 use crate::entry::{Entry, EntryCommitted, EntrySealed};
+use crate::metrics::{CacheKind, ServerMetrics};
 use crate::prelude::*;
 use crate::value::{IndexType, Value};
 
@@ -55,8 +58,25 @@ enum NameCacheValue {
     S(Box<Value>),
 }
 
+/// Counts the items that a write transaction evicted from a cache, so that this can be
+/// reported in the server metrics when the transaction commits.
+#[derive(Debug, Default)]
+struct EvictionStat {
+    evicted: u64,
+}
+
+impl<K> ARCacheWriteStat<K> for EvictionStat {
+    fn evict_from_recent(&mut self, _k: &K) {
+        self.evicted += 1;
+    }
+
+    fn evict_from_frequent(&mut self, _k: &K) {
+        self.evicted += 1;
+    }
+}
+
 pub struct IdlArcSqlite {
-    db: IdlSqlite,
+    db: IdlStorage,
     entry_cache: ARCache<u64, Arc<EntrySealedCommitted>>,
     idl_cache: ARCache<IdlCacheKey, Box<IDLBitRange>>,
     name_cache: ARCache<NameCacheKey, NameCacheValue>,
@@ -67,22 +87,26 @@ pub struct IdlArcSqlite {
     allids: CowCell<IDLBitRange>,
     maxid: CowCell<u64>,
     keyhandles: CowCell<HashMap<KeyHandleId, KeyHandle>>,
+
+    metrics: Arc<ServerMetrics>,
 }
 
 pub struct IdlArcSqliteReadTransaction<'a> {
-    db: IdlSqliteReadTransaction,
+    db: IdlStorageRead,
     entry_cache: ARCacheReadTxn<'a, u64, Arc<EntrySealedCommitted>, ()>,
     idl_cache: ARCacheReadTxn<'a, IdlCacheKey, Box<IDLBitRange>, ()>,
     name_cache: ARCacheReadTxn<'a, NameCacheKey, NameCacheValue, ()>,
 
     idx_exists_cache: ARCacheReadTxn<'a, IdxNameKey, bool, ()>,
     allids: CowCellReadTxn<IDLBitRange>,
+
+    metrics: &'a ServerMetrics,
 }
 
 pub struct IdlArcSqliteWriteTransaction<'a> {
-    pub(super) db: IdlSqliteWriteTransaction,
-    entry_cache: ARCacheWriteTxn<'a, u64, Arc<EntrySealedCommitted>, ()>,
-    idl_cache: ARCacheWriteTxn<'a, IdlCacheKey, Box<IDLBitRange>, ()>,
+    pub(super) db: IdlStorageWrite,
+    entry_cache: ARCacheWriteTxn<'a, u64, Arc<EntrySealedCommitted>, EvictionStat>,
+    idl_cache: ARCacheWriteTxn<'a, IdlCacheKey, Box<IDLBitRange>, EvictionStat>,
     name_cache: ARCacheWriteTxn<'a, NameCacheKey, NameCacheValue, ()>,
 
     idx_exists_cache: ARCacheWriteTxn<'a, IdxNameKey, bool, ()>,
@@ -91,6 +115,8 @@ pub struct IdlArcSqliteWriteTransaction<'a> {
     allids: CowCellWriteTxn<'a, IDLBitRange>,
     maxid: CowCellWriteTxn<'a, u64>,
     pub(super) keyhandles: CowCellWriteTxn<'a, HashMap<KeyHandleId, KeyHandle>>,
+
+    metrics: &'a ServerMetrics,
 }
 
 macro_rules! get_identry {
@@ -113,6 +139,12 @@ macro_rules! get_identry {
                     }
                 });
 
+                $self.metrics.record_cache_lookups(
+                    CacheKind::Entry,
+                    result.len() as u64,
+                    nidl.len() as u64,
+                );
+
                 if !nidl.is_empty() {
                     // Now, get anything from nidl that is needed.
                     let mut db_result = $self.db.get_identry(&IdList::Partial(nidl))?;
@@ -139,6 +171,12 @@ macro_rules! get_identry {
                         None => unsafe { nidl.push_id(i) },
                     });
 
+                $self.metrics.record_cache_lookups(
+                    CacheKind::Entry,
+                    result.len() as u64,
+                    nidl.len() as u64,
+                );
+
                 if !nidl.is_empty() {
                     // Now, get anything from nidl that is needed.
                     let mut db_result = $self.db.get_identry(&IdList::Partial(nidl))?;
@@ -206,8 +244,10 @@ macro_rules! get_idl {
                 attr = ?$attr,
                 idl = %data,
             );
+            $self.metrics.record_cache_lookups(CacheKind::Idl, 1, 0);
             return Ok(Some(data.as_ref().clone()));
         }
+        $self.metrics.record_cache_lookups(CacheKind::Idl, 0, 1);
 
         // If it was a miss, does the  actually exist in the DB?
         let idx_key = IdxNameKey {
@@ -654,6 +694,7 @@ impl IdlArcSqliteWriteTransaction<'_> {
             allids,
             maxid,
             keyhandles,
+            metrics,
         } = self;
 
         // Write any dirty items to the disk.
@@ -724,12 +765,15 @@ impl IdlArcSqliteWriteTransaction<'_> {
         op_ts_max.commit();
         name_cache.commit();
         idx_exists_cache.commit();
-        idl_cache.commit();
+        let idl_stat = idl_cache.commit();
         allids.commit();
         maxid.commit();
         keyhandles.commit();
         // Unlock the entry cache last to remove contention on everything else.
-        entry_cache.commit();
+        let entry_stat = entry_cache.commit();
+
+        metrics.record_cache_evictions(CacheKind::Idl, idl_stat.evicted);
+        metrics.record_cache_evictions(CacheKind::Entry, entry_stat.evicted);
 
         Ok(())
     }
@@ -1238,6 +1282,10 @@ impl IdlArcSqliteWriteTransaction<'_> {
         self.db.set_db_index_version(v)
     }
 
+    pub fn compact(&self) -> Result<DbCompaction, OperationError> {
+        self.db.compact()
+    }
+
     pub fn setup(&mut self) -> Result<(), OperationError> {
         self.db
             .setup()
@@ -1253,8 +1301,12 @@ impl IdlArcSqliteWriteTransaction<'_> {
 }
 
 impl IdlArcSqlite {
-    pub fn new(cfg: &BackendConfig, vacuum: bool) -> Result<Self, OperationError> {
-        let db = IdlSqlite::new(cfg, vacuum)?;
+    pub fn new(
+        cfg: &BackendConfig,
+        vacuum: bool,
+        metrics: Arc<ServerMetrics>,
+    ) -> Result<Self, OperationError> {
+        let db = IdlStorage::new(cfg, vacuum)?;
 
         // Autotune heuristic.
         let mut cache_size = cfg.arcsize.unwrap_or_else(|| {
@@ -1278,6 +1330,14 @@ impl IdlArcSqlite {
             cache_size = DEFAULT_CACHE_TARGET; // this being above the log was an uncaught bug
         }
 
+        info!(
+            entry_cache = cache_size,
+            idl_cache = cache_size * DEFAULT_IDL_CACHE_RATIO,
+            "Configured backend cache sizes"
+        );
+        metrics.set_cache_capacity(CacheKind::Entry, cache_size);
+        metrics.set_cache_capacity(CacheKind::Idl, cache_size * DEFAULT_IDL_CACHE_RATIO);
+
         let entry_cache = ARCacheBuilder::new()
             .set_expected_workload(
                 cache_size,
@@ -1357,6 +1417,7 @@ impl IdlArcSqlite {
             allids,
             maxid,
             keyhandles,
+            metrics,
         })
     }
 
@@ -1382,14 +1443,15 @@ impl IdlArcSqlite {
             name_cache: name_cache_read,
             idx_exists_cache: idx_exists_cache_read,
             allids: allids_read,
+            metrics: &self.metrics,
         })
     }
 
     pub fn write(&self) -> Result<IdlArcSqliteWriteTransaction, OperationError> {
         // IMPORTANT! Always take entrycache FIRST
-        let entry_cache_write = self.entry_cache.write();
+        let entry_cache_write = self.entry_cache.write_stats(EvictionStat::default());
         let db_write = self.db.write()?;
-        let idl_cache_write = self.idl_cache.write();
+        let idl_cache_write = self.idl_cache.write_stats(EvictionStat::default());
         let name_cache_write = self.name_cache.write();
         let idx_exists_cache_write = self.idx_exists_cache.write();
         let op_ts_max_write = self.op_ts_max.write();
@@ -1407,9 +1469,17 @@ impl IdlArcSqlite {
             allids: allids_write,
             maxid: maxid_write,
             keyhandles: keyhandles_write,
+            metrics: &self.metrics,
         })
     }
 
+    pub(crate) fn set_data_keys(
+        &self,
+        data_keys: DataEncryptionKeys,
+    ) -> Result<(), OperationError> {
+        self.db.set_data_keys(data_keys)
+    }
+
     /*
     pub fn stats_audit(&self, audit: &mut AuditScope) {
         let entry_stats = self.entry_cache.view_stats();
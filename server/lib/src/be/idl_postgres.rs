@@ -0,0 +1,1437 @@
+//! Storage of the database in PostgreSQL. This mirrors the tables of `idl_sqlite`, so that the
+//! content of the database is the same regardless of where it is stored, and a backup of one can
+//! be restored into the other.
+//!
+//! The client that is used is synchronous, like the rest of the backend. It drives each
+//! connection with its own small runtime, so calls to the database from threads that are in a
+//! runtime are made from a thread of their own.
+
+use std::cell::{RefCell, RefMut};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::{Arc, LockResult, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use hashbrown::HashMap;
+use idlset::v2::IDLBitRange;
+use kanidm_proto::internal::{ConsistencyError, OperationError};
+use openssl::ssl::{SslConnector, SslMethod};
+use postgres::types::ToSql;
+use postgres::{Client, Row};
+use postgres_openssl::MakeTlsConnector;
+use uuid::Uuid;
+
+use crate::be::dbentry::DbIdentSpn;
+use crate::be::dbvalue::DbCidV1;
+use crate::be::encryption::DataEncryptionKeys;
+use crate::be::idl_sqlite::serde_json_error;
+use crate::be::keystorage::{KeyHandle, KeyHandleId};
+use crate::be::{
+    BackendConfig, DbCompaction, IdList, IdRawEntry, IdxKey, IdxSlope, PostgresConfig,
+};
+use crate::prelude::*;
+use crate::value::{IndexType, Value};
+
+const DBV_ID2ENTRY: &str = "id2entry";
+const DBV_INDEXV: &str = "indexv";
+
+/// The version of the id2entry layout that is created in a new database. This is the same as
+/// the latest version of `idl_sqlite`, which PostgreSQL support started from.
+const DBV_ID2ENTRY_CURRENT: i64 = 10;
+
+/// The key of the advisory lock that is held by a write transaction. This stops two servers
+/// that are configured with the same database from writing to it at the same time.
+const WRITE_LOCK_KEY: i64 = 0x6b61_6e69_646d;
+
+#[allow(clippy::needless_pass_by_value)] // needs to accept value from `map_err`
+pub(super) fn postgres_error(e: postgres::Error) -> OperationError {
+    admin_error!(?e, "PostgreSQL Error");
+    OperationError::PostgresError
+}
+
+/// The client runs its own runtime to drive the connection, which can not be started on a
+/// thread that is already in a runtime of any flavour. Calls from those threads are made from a
+/// scoped thread instead, which borrows the connection until the call returns.
+fn run_blocking<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    if tokio::runtime::Handle::try_current().is_err() {
+        return f();
+    }
+
+    std::thread::scope(|scope| match scope.spawn(f).join() {
+        Ok(r) => r,
+        Err(panic) => std::panic::resume_unwind(panic),
+    })
+}
+
+/// Closing a connection notifies the database from the runtime of the connection, so this
+/// must also be done outside of the runtime of the server.
+fn close_conn(conn: Client) {
+    run_blocking(move || drop(conn))
+}
+
+fn to_entry_id(id: i64) -> Result<u64, OperationError> {
+    if id <= 0 {
+        return Err(OperationError::InvalidEntryId);
+    }
+    u64::try_from(id).map_err(|_| OperationError::InvalidEntryId)
+}
+
+fn from_entry_id(id: u64) -> Result<i64, OperationError> {
+    if id == 0 {
+        return Err(OperationError::InvalidEntryId);
+    }
+    i64::try_from(id).map_err(|_| OperationError::InvalidEntryId)
+}
+
+fn row_to_raw_entry(row: &Row) -> Result<IdRawEntry, OperationError> {
+    let id: i64 = row.try_get(0).map_err(postgres_error)?;
+    let data: Vec<u8> = row.try_get(1).map_err(postgres_error)?;
+    Ok(IdRawEntry {
+        id: to_entry_id(id)?,
+        data,
+    })
+}
+
+/// Opens new connections to the database, both to fill the pool and to replace connections
+/// that were closed, such as when the database fails over.
+struct PostgresConnector {
+    config: postgres::Config,
+    tls: MakeTlsConnector,
+}
+
+impl PostgresConnector {
+    fn new(pg_cfg: &PostgresConfig) -> Result<Self, OperationError> {
+        let mut config: postgres::Config = pg_cfg.url.parse().map_err(|e| {
+            error!(?e, "Invalid PostgreSQL database url");
+            OperationError::InvalidState
+        })?;
+
+        if let Some(password) = pg_cfg.password.as_deref() {
+            config.password(password);
+        }
+
+        // The server is verified with the system trust store, by the host that the url names.
+        let tls = SslConnector::builder(SslMethod::tls_client())
+            .map(|builder| MakeTlsConnector::new(builder.build()))
+            .map_err(|e| {
+                error!(?e, "Unable to configure TLS for PostgreSQL");
+                OperationError::InvalidState
+            })?;
+
+        Ok(PostgresConnector { config, tls })
+    }
+
+    fn connect(&self) -> Result<Client, OperationError> {
+        run_blocking(|| self.config.connect(self.tls.clone())).map_err(|e| {
+            admin_error!(?e, "Failed to connect to PostgreSQL");
+            OperationError::PostgresError
+        })
+    }
+}
+
+/// The idle connections of the pool.
+struct Connections(Mutex<VecDeque<Client>>);
+
+impl Connections {
+    fn lock(&self) -> LockResult<MutexGuard<'_, VecDeque<Client>>> {
+        self.0.lock()
+    }
+}
+
+impl Drop for Connections {
+    fn drop(&mut self) {
+        let conns = match self.0.get_mut() {
+            Ok(conns) => std::mem::take(conns),
+            Err(poisoned) => std::mem::take(poisoned.into_inner()),
+        };
+        run_blocking(move || drop(conns))
+    }
+}
+
+type ConnPool = Arc<Connections>;
+type DataKeys = Arc<OnceLock<DataEncryptionKeys>>;
+
+#[derive(Clone)]
+pub struct IdlPostgres {
+    pool: ConnPool,
+    connector: Arc<PostgresConnector>,
+    schema: &'static str,
+    data_keys: DataKeys,
+}
+
+pub struct IdlPostgresReadTransaction {
+    pool: ConnPool,
+    conn: RefCell<Option<Client>>,
+    schema: &'static str,
+    data_keys: DataKeys,
+}
+
+pub struct IdlPostgresWriteTransaction {
+    pool: ConnPool,
+    conn: RefCell<Option<Client>>,
+    schema: &'static str,
+    data_keys: DataKeys,
+}
+
+pub(crate) trait IdlPostgresTransaction {
+    fn get_schema(&self) -> &str;
+
+    fn get_conn(&self) -> Result<RefMut<'_, Client>, OperationError>;
+
+    fn get_data_keys(&self) -> Option<&DataEncryptionKeys>;
+
+    /// The quoted name of a table in the schema of this database.
+    fn table(&self, name: &str) -> String {
+        format!("\"{}\".\"{}\"", self.get_schema(), name)
+    }
+
+    fn query(
+        &self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, OperationError> {
+        let mut conn = self.get_conn()?;
+        let client: &mut Client = &mut conn;
+        run_blocking(|| client.query(query, params)).map_err(postgres_error)
+    }
+
+    fn query_opt(
+        &self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Option<Row>, OperationError> {
+        let mut conn = self.get_conn()?;
+        let client: &mut Client = &mut conn;
+        run_blocking(|| client.query_opt(query, params)).map_err(postgres_error)
+    }
+
+    fn execute(&self, query: &str, params: &[&(dyn ToSql + Sync)]) -> Result<(), OperationError> {
+        let mut conn = self.get_conn()?;
+        let client: &mut Client = &mut conn;
+        run_blocking(|| client.execute(query, params))
+            .map(|_| ())
+            .map_err(postgres_error)
+    }
+
+    fn get_identry_raw(&self, idl: &IdList) -> Result<Vec<IdRawEntry>, OperationError> {
+        let rows = match idl {
+            IdList::AllIds => self.query(
+                &format!("SELECT id, data FROM {}", self.table("id2entry")),
+                &[],
+            )?,
+            IdList::Partial(idli) | IdList::PartialThreshold(idli) | IdList::Indexed(idli) => {
+                let id_list = idli
+                    .into_iter()
+                    .map(|id| i64::try_from(id).map_err(|_| OperationError::InvalidEntryId))
+                    .collect::<Result<Vec<i64>, _>>()?;
+
+                self.query(
+                    &format!(
+                        "SELECT id, data FROM {} WHERE id = ANY($1)",
+                        self.table("id2entry")
+                    ),
+                    &[&id_list],
+                )?
+            }
+        };
+
+        rows.iter().map(row_to_raw_entry).collect()
+    }
+
+    fn exists_table(&self, tname: &str) -> Result<bool, OperationError> {
+        self.query_opt(
+            "SELECT 1 FROM information_schema.tables WHERE table_schema = $1 AND table_name = $2",
+            &[&self.get_schema(), &tname],
+        )
+        .map(|row| row.is_some())
+    }
+
+    fn exists_idx(&self, attr: &Attribute, itype: IndexType) -> Result<bool, OperationError> {
+        let tname = format!("idx_{}_{}", itype.as_idx_str(), attr.as_str());
+        self.exists_table(&tname)
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    fn get_idl(
+        &self,
+        attr: &Attribute,
+        itype: IndexType,
+        idx_key: &str,
+    ) -> Result<Option<IDLBitRange>, OperationError> {
+        if !(self.exists_idx(attr, itype)?) {
+            debug!(
+                "IdlPostgresTransaction: Index {:?} {:?} not found",
+                itype, attr
+            );
+            return Ok(None);
+        }
+
+        let tname = format!("idx_{}_{}", itype.as_idx_str(), attr.as_str());
+        let idl_raw: Option<Vec<u8>> = self
+            .query_opt(
+                &format!("SELECT idl FROM {} WHERE key = $1", self.table(&tname)),
+                &[&idx_key],
+            )?
+            .map(|row| row.try_get(0))
+            .transpose()
+            .map_err(postgres_error)?;
+
+        let idl = match idl_raw {
+            Some(d) => serde_json::from_slice(d.as_slice()).map_err(serde_json_error)?,
+            // We don't have this value, it must be empty (or we
+            // have a corrupted index .....
+            None => IDLBitRange::new(),
+        };
+        trace!(
+            miss_index = ?itype,
+            attr = ?attr,
+            idl = %idl,
+        );
+
+        Ok(Some(idl))
+    }
+
+    fn get_uuid_by(
+        &self,
+        table: &str,
+        column: &str,
+        key: &str,
+    ) -> Result<Option<Uuid>, OperationError> {
+        let uuid_raw: Option<String> = self
+            .query_opt(
+                &format!(
+                    "SELECT uuid FROM {} WHERE {} = $1",
+                    self.table(table),
+                    column
+                ),
+                &[&key],
+            )?
+            .map(|row| row.try_get(0))
+            .transpose()
+            .map_err(postgres_error)?;
+
+        Ok(uuid_raw.as_ref().and_then(|u| Uuid::parse_str(u).ok()))
+    }
+
+    fn name2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError> {
+        self.get_uuid_by("idx_name2uuid", "name", name)
+    }
+
+    fn externalid2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError> {
+        self.get_uuid_by("idx_externalid2uuid", "eid", name)
+    }
+
+    fn uuid2spn(&mut self, uuid: Uuid) -> Result<Option<Value>, OperationError> {
+        let uuids = uuid.as_hyphenated().to_string();
+        let spn_raw: Option<Vec<u8>> = self
+            .query_opt(
+                &format!(
+                    "SELECT spn FROM {} WHERE uuid = $1",
+                    self.table("idx_uuid2spn")
+                ),
+                &[&uuids],
+            )?
+            .map(|row| row.try_get(0))
+            .transpose()
+            .map_err(postgres_error)?;
+
+        spn_raw
+            .map(|d| {
+                serde_json::from_slice::<DbIdentSpn>(d.as_slice())
+                    .map(Value::from)
+                    .map_err(serde_json_error)
+            })
+            .transpose()
+    }
+
+    fn uuid2rdn(&mut self, uuid: Uuid) -> Result<Option<String>, OperationError> {
+        let uuids = uuid.as_hyphenated().to_string();
+        self.query_opt(
+            &format!(
+                "SELECT rdn FROM {} WHERE uuid = $1",
+                self.table("idx_uuid2rdn")
+            ),
+            &[&uuids],
+        )?
+        .map(|row| row.try_get(0))
+        .transpose()
+        .map_err(postgres_error)
+    }
+
+    /// Read a value from one of the single row metadata tables.
+    fn get_db_meta<T: serde::de::DeserializeOwned>(
+        &self,
+        table: &str,
+        id: i64,
+    ) -> Result<Option<T>, OperationError> {
+        let data: Option<Vec<u8>> = self
+            .query_opt(
+                &format!("SELECT data FROM {} WHERE id = $1", self.table(table)),
+                &[&id],
+            )?
+            .map(|row| row.try_get(0))
+            .transpose()
+            .map_err(postgres_error)?;
+
+        data.map(|d| serde_json::from_slice(d.as_slice()).map_err(serde_json_error))
+            .transpose()
+    }
+
+    fn get_db_s_uuid(&self) -> Result<Option<Uuid>, OperationError> {
+        self.get_db_meta("db_sid", 2)
+    }
+
+    fn get_db_d_uuid(&self) -> Result<Option<Uuid>, OperationError> {
+        self.get_db_meta("db_did", 2)
+    }
+
+    fn get_db_ts_max(&self) -> Result<Option<Duration>, OperationError> {
+        self.get_db_meta("db_op_ts", 1)
+    }
+
+    fn get_key_handles(&mut self) -> Result<BTreeMap<KeyHandleId, KeyHandle>, OperationError> {
+        self.query(
+            &format!("SELECT id, data FROM {}", self.table("keyhandles")),
+            &[],
+        )?
+        .iter()
+        .map(|row| {
+            let id: Vec<u8> = row.try_get(0).map_err(postgres_error)?;
+            let data: Vec<u8> = row.try_get(1).map_err(postgres_error)?;
+            let id = serde_json::from_slice(id.as_slice()).map_err(serde_json_error)?;
+            let data = serde_json::from_slice(data.as_slice()).map_err(serde_json_error)?;
+            Ok((id, data))
+        })
+        .collect()
+    }
+
+    #[instrument(level = "debug", name = "idl_postgres::get_allids", skip_all)]
+    fn get_allids(&self) -> Result<IDLBitRange, OperationError> {
+        let mut ids = self
+            .query(&format!("SELECT id FROM {}", self.table("id2entry")), &[])?
+            .iter()
+            .map(|row| {
+                row.try_get::<_, i64>(0)
+                    .map_err(postgres_error)
+                    .and_then(to_entry_id)
+            })
+            .collect::<Result<IDLBitRange, _>>()?;
+        ids.compress();
+        Ok(ids)
+    }
+
+    fn list_idxs(&self) -> Result<Vec<String>, OperationError> {
+        self.query(
+            "SELECT table_name::text FROM information_schema.tables
+             WHERE table_schema = $1 AND table_name LIKE 'idx_%'",
+            &[&self.get_schema()],
+        )?
+        .iter()
+        .map(|row| row.try_get(0).map_err(postgres_error))
+        .collect()
+    }
+
+    fn list_quarantined(&self) -> Result<Vec<(u64, String)>, OperationError> {
+        self.query(
+            &format!("SELECT id, data FROM {}", self.table("id2entry_quarantine")),
+            &[],
+        )?
+        .iter()
+        .map(|row| {
+            row_to_raw_entry(row)
+                .and_then(|data| data.into_dbentry().map(|(id, db_e)| (id, db_e.to_string())))
+        })
+        .collect()
+    }
+
+    fn list_index_content(
+        &self,
+        index_name: &str,
+    ) -> Result<Vec<(String, IDLBitRange)>, OperationError> {
+        self.query(
+            &format!("SELECT key, idl FROM {}", self.table(index_name)),
+            &[],
+        )?
+        .iter()
+        .map(|row| {
+            let key: String = row.try_get(0).map_err(postgres_error)?;
+            let data: Vec<u8> = row.try_get(1).map_err(postgres_error)?;
+            serde_json::from_slice(data.as_slice())
+                .map_err(serde_json_error)
+                .map(|idl| (key, idl))
+        })
+        .collect()
+    }
+
+    /// PostgreSQL checks the integrity of its own storage, so this only confirms that the
+    /// database can be reached and that the entry table is readable.
+    fn verify(&self) -> Vec<Result<(), ConsistencyError>> {
+        match self.query_opt(
+            &format!("SELECT COUNT(id) FROM {}", self.table("id2entry")),
+            &[],
+        ) {
+            Ok(Some(_)) => Vec::with_capacity(0),
+            _ => vec![Err(ConsistencyError::PostgresIntegrityFailure)],
+        }
+    }
+}
+
+fn borrow_conn(conn: &RefCell<Option<Client>>) -> Result<RefMut<'_, Client>, OperationError> {
+    let guard = conn.try_borrow_mut().map_err(|_| {
+        error!("PostgreSQL connection is already in use by this transaction");
+        OperationError::BackendEngine
+    })?;
+    RefMut::filter_map(guard, |c| c.as_mut())
+        .map_err(|_| OperationError::TransactionAlreadyCommitted)
+}
+
+/// Abort the transaction and return the connection to the pool. A connection that failed is
+/// still returned, and is replaced when it is next taken from the pool.
+fn release_conn(pool: &ConnPool, conn: &RefCell<Option<Client>>) {
+    if let Some(mut client) = conn.borrow_mut().take() {
+        if let Err(e) = run_blocking(|| client.batch_execute("ROLLBACK")) {
+            admin_error!(?e, "Unable to rollback PostgreSQL transaction");
+        }
+
+        match pool.lock() {
+            Ok(mut guard) => guard.push_back(client),
+            Err(err) => error!(?err, "Unable to return connection to pool"),
+        }
+    }
+}
+
+impl IdlPostgresTransaction for IdlPostgresReadTransaction {
+    fn get_schema(&self) -> &str {
+        self.schema
+    }
+
+    fn get_conn(&self) -> Result<RefMut<'_, Client>, OperationError> {
+        borrow_conn(&self.conn)
+    }
+
+    fn get_data_keys(&self) -> Option<&DataEncryptionKeys> {
+        self.data_keys.get()
+    }
+}
+
+impl Drop for IdlPostgresReadTransaction {
+    // Abort
+    fn drop(&mut self) {
+        release_conn(&self.pool, &self.conn);
+    }
+}
+
+impl IdlPostgresReadTransaction {
+    fn new(
+        pool: ConnPool,
+        mut conn: Client,
+        schema: &'static str,
+        data_keys: DataKeys,
+    ) -> Result<Self, OperationError> {
+        // Readers see a consistent snapshot for the length of their transaction.
+        if let Err(e) =
+            run_blocking(|| conn.batch_execute("BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY"))
+        {
+            release_pooled(&pool, conn);
+            return Err(postgres_error(e));
+        }
+
+        Ok(IdlPostgresReadTransaction {
+            pool,
+            conn: RefCell::new(Some(conn)),
+            schema,
+            data_keys,
+        })
+    }
+}
+
+impl IdlPostgresTransaction for IdlPostgresWriteTransaction {
+    fn get_schema(&self) -> &str {
+        self.schema
+    }
+
+    fn get_conn(&self) -> Result<RefMut<'_, Client>, OperationError> {
+        borrow_conn(&self.conn)
+    }
+
+    fn get_data_keys(&self) -> Option<&DataEncryptionKeys> {
+        self.data_keys.get()
+    }
+}
+
+impl Drop for IdlPostgresWriteTransaction {
+    // Abort
+    fn drop(&mut self) {
+        release_conn(&self.pool, &self.conn);
+    }
+}
+
+/// Return a connection that never started a transaction to the pool.
+fn release_pooled(pool: &ConnPool, conn: Client) {
+    match pool.lock() {
+        Ok(mut guard) => guard.push_back(conn),
+        Err(err) => error!(?err, "Unable to return connection to pool"),
+    }
+}
+
+impl IdlPostgresWriteTransaction {
+    fn new(
+        pool: ConnPool,
+        mut conn: Client,
+        schema: &'static str,
+        data_keys: DataKeys,
+    ) -> Result<Self, OperationError> {
+        // Writes are already serialised within this server. The lock serialises them with any
+        // other server that has been wrongly configured to use the same database. This uses
+        // read committed, so that the writer sees everything committed while it waited.
+        if let Err(e) = run_blocking(|| {
+            conn.batch_execute(&format!(
+                "BEGIN ISOLATION LEVEL READ COMMITTED;
+                 SELECT pg_advisory_xact_lock({WRITE_LOCK_KEY});"
+            ))
+        }) {
+            release_pooled(&pool, conn);
+            return Err(postgres_error(e));
+        }
+
+        Ok(IdlPostgresWriteTransaction {
+            pool,
+            conn: RefCell::new(Some(conn)),
+            schema,
+            data_keys,
+        })
+    }
+
+    #[instrument(level = "debug", name = "idl_postgres::commit", skip_all)]
+    pub fn commit(self) -> Result<(), OperationError> {
+        let Some(mut conn) = self.conn.borrow_mut().take() else {
+            return Err(OperationError::TransactionAlreadyCommitted);
+        };
+
+        let r = run_blocking(|| conn.batch_execute("COMMIT")).map_err(|e| {
+            admin_error!(?e, "CRITICAL: failed to commit postgres txn");
+            OperationError::BackendEngine
+        });
+
+        release_pooled(&self.pool, conn);
+        r
+    }
+
+    pub fn get_id2entry_max_id(&self) -> Result<u64, OperationError> {
+        let max_id: Option<i64> = self
+            .query_opt(
+                &format!("SELECT MAX(id) FROM {}", self.table("id2entry")),
+                &[],
+            )?
+            .map(|row| row.try_get(0))
+            .transpose()
+            .map_err(postgres_error)?
+            .flatten();
+
+        // No rows are present, return a 0.
+        max_id
+            .unwrap_or(0)
+            .try_into()
+            .map_err(|_| OperationError::InvalidEntryId)
+    }
+
+    pub fn write_identries_raw<I>(&self, mut entries: I) -> Result<(), OperationError>
+    where
+        I: Iterator<Item = IdRawEntry>,
+    {
+        let query = format!(
+            "INSERT INTO {} (id, data) VALUES($1, $2)
+             ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data",
+            self.table("id2entry")
+        );
+
+        entries.try_for_each(|e| {
+            let id = from_entry_id(e.id)?;
+            self.execute(&query, &[&id, &e.data])
+        })
+    }
+
+    pub fn delete_identry(&self, id: u64) -> Result<(), OperationError> {
+        let iid = from_entry_id(id)?;
+        self.execute(
+            &format!("DELETE FROM {} WHERE id = $1", self.table("id2entry")),
+            &[&iid],
+        )
+    }
+
+    pub fn write_idl(
+        &self,
+        attr: &Attribute,
+        itype: IndexType,
+        idx_key: &str,
+        idl: &IDLBitRange,
+    ) -> Result<(), OperationError> {
+        let tname = self.table(&format!("idx_{}_{}", itype.as_idx_str(), attr.as_str()));
+
+        if idl.is_empty() {
+            // Delete this idx_key from the table.
+            self.execute(&format!("DELETE FROM {tname} WHERE key = $1"), &[&idx_key])
+        } else {
+            // The idl is serialised in the same way as in SQLite.
+            let idl_raw = serde_json::to_vec(idl).map_err(serde_json_error)?;
+
+            self.execute(
+                &format!(
+                    "INSERT INTO {tname} (key, idl) VALUES($1, $2)
+                     ON CONFLICT (key) DO UPDATE SET idl = EXCLUDED.idl"
+                ),
+                &[&idx_key, &idl_raw],
+            )
+        }
+    }
+
+    fn create_table(&self, name: &str, columns: &str) -> Result<(), OperationError> {
+        self.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} ({})",
+                self.table(name),
+                columns
+            ),
+            &[],
+        )
+    }
+
+    fn write_uuid_by(
+        &self,
+        table: &str,
+        column: &str,
+        key: &str,
+        uuid: Option<Uuid>,
+    ) -> Result<(), OperationError> {
+        match uuid {
+            Some(uuid) => {
+                let uuids = uuid.as_hyphenated().to_string();
+                self.execute(
+                    &format!(
+                        "INSERT INTO {} ({column}, uuid) VALUES($1, $2)
+                         ON CONFLICT ({column}) DO UPDATE SET uuid = EXCLUDED.uuid",
+                        self.table(table)
+                    ),
+                    &[&key, &uuids],
+                )
+            }
+            None => self.execute(
+                &format!("DELETE FROM {} WHERE {column} = $1", self.table(table)),
+                &[&key],
+            ),
+        }
+    }
+
+    pub fn create_name2uuid(&self) -> Result<(), OperationError> {
+        self.create_table("idx_name2uuid", "name TEXT PRIMARY KEY, uuid TEXT")
+    }
+
+    pub fn write_name2uuid_add(&self, name: &str, uuid: Uuid) -> Result<(), OperationError> {
+        self.write_uuid_by("idx_name2uuid", "name", name, Some(uuid))
+    }
+
+    pub fn write_name2uuid_rem(&self, name: &str) -> Result<(), OperationError> {
+        self.write_uuid_by("idx_name2uuid", "name", name, None)
+    }
+
+    pub fn create_externalid2uuid(&self) -> Result<(), OperationError> {
+        self.create_table("idx_externalid2uuid", "eid TEXT PRIMARY KEY, uuid TEXT")
+    }
+
+    pub fn write_externalid2uuid_add(&self, name: &str, uuid: Uuid) -> Result<(), OperationError> {
+        self.write_uuid_by("idx_externalid2uuid", "eid", name, Some(uuid))
+    }
+
+    pub fn write_externalid2uuid_rem(&self, name: &str) -> Result<(), OperationError> {
+        self.write_uuid_by("idx_externalid2uuid", "eid", name, None)
+    }
+
+    pub fn create_uuid2spn(&self) -> Result<(), OperationError> {
+        self.create_table("idx_uuid2spn", "uuid TEXT PRIMARY KEY, spn BYTEA")
+    }
+
+    pub fn write_uuid2spn(&self, uuid: Uuid, k: Option<&Value>) -> Result<(), OperationError> {
+        let uuids = uuid.as_hyphenated().to_string();
+        match k {
+            Some(k) => {
+                let dbv1: DbIdentSpn = k.to_db_ident_spn();
+                let data = serde_json::to_vec(&dbv1).map_err(serde_json_error)?;
+                self.execute(
+                    &format!(
+                        "INSERT INTO {} (uuid, spn) VALUES($1, $2)
+                         ON CONFLICT (uuid) DO UPDATE SET spn = EXCLUDED.spn",
+                        self.table("idx_uuid2spn")
+                    ),
+                    &[&uuids, &data],
+                )
+            }
+            None => self.execute(
+                &format!("DELETE FROM {} WHERE uuid = $1", self.table("idx_uuid2spn")),
+                &[&uuids],
+            ),
+        }
+    }
+
+    pub fn create_uuid2rdn(&self) -> Result<(), OperationError> {
+        self.create_table("idx_uuid2rdn", "uuid TEXT PRIMARY KEY, rdn TEXT")
+    }
+
+    pub fn write_uuid2rdn(&self, uuid: Uuid, k: Option<&String>) -> Result<(), OperationError> {
+        let uuids = uuid.as_hyphenated().to_string();
+        match k {
+            Some(k) => self.execute(
+                &format!(
+                    "INSERT INTO {} (uuid, rdn) VALUES($1, $2)
+                     ON CONFLICT (uuid) DO UPDATE SET rdn = EXCLUDED.rdn",
+                    self.table("idx_uuid2rdn")
+                ),
+                &[&uuids, k],
+            ),
+            None => self.execute(
+                &format!("DELETE FROM {} WHERE uuid = $1", self.table("idx_uuid2rdn")),
+                &[&uuids],
+            ),
+        }
+    }
+
+    pub fn get_db_ruv(&self) -> Result<BTreeSet<Cid>, OperationError> {
+        self.query(&format!("SELECT cid FROM {}", self.table("ruv")), &[])?
+            .iter()
+            .map(|row| {
+                let ser_cid: String = row.try_get(0).map_err(postgres_error)?;
+                let db_cid: DbCidV1 = serde_json::from_str(&ser_cid).map_err(serde_json_error)?;
+                Ok(db_cid.into())
+            })
+            .collect()
+    }
+
+    pub fn write_db_ruv<I, J>(&mut self, mut added: I, mut removed: J) -> Result<(), OperationError>
+    where
+        I: Iterator<Item = Cid>,
+        J: Iterator<Item = Cid>,
+    {
+        let delete = format!("DELETE FROM {} WHERE cid = $1", self.table("ruv"));
+        removed.try_for_each(|cid| {
+            let db_cid: DbCidV1 = cid.into();
+            serde_json::to_string(&db_cid)
+                .map_err(serde_json_error)
+                .and_then(|ser_cid| self.execute(&delete, &[&ser_cid]))
+        })?;
+
+        let insert = format!(
+            "INSERT INTO {} (cid) VALUES($1) ON CONFLICT (cid) DO NOTHING",
+            self.table("ruv")
+        );
+        added.try_for_each(|cid| {
+            let db_cid: DbCidV1 = cid.into();
+            serde_json::to_string(&db_cid)
+                .map_err(serde_json_error)
+                .and_then(|ser_cid| self.execute(&insert, &[&ser_cid]))
+        })
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub fn create_idx(&self, attr: &Attribute, itype: IndexType) -> Result<(), OperationError> {
+        self.create_table(
+            &format!("idx_{}_{}", itype.as_idx_str(), attr.as_str()),
+            "key TEXT PRIMARY KEY, idl BYTEA",
+        )
+    }
+
+    /// ⚠️  - This function will destroy all indexes in the database.
+    ///
+    /// It should only be called internally by the backend in limited and
+    /// specific situations.
+    #[instrument(level = "trace", skip_all)]
+    pub fn danger_purge_idxs(&self) -> Result<(), OperationError> {
+        let idx_table_list = self.list_idxs()?;
+        trace!(tables = ?idx_table_list);
+
+        idx_table_list.iter().try_for_each(|idx_table| {
+            debug!(table = ?idx_table, "removing idx_table");
+            self.execute(&format!("DROP TABLE {}", self.table(idx_table)), &[])
+        })
+    }
+
+    pub fn store_idx_slope_analysis(
+        &self,
+        slopes: &HashMap<IdxKey, IdxSlope>,
+    ) -> Result<(), OperationError> {
+        self.create_table("idxslope_analysis", "id TEXT PRIMARY KEY, slope SMALLINT")?;
+
+        // Remove any data if it exists.
+        self.execute(
+            &format!("DELETE FROM {}", self.table("idxslope_analysis")),
+            &[],
+        )?;
+
+        let insert = format!(
+            "INSERT INTO {} (id, slope) VALUES($1, $2)",
+            self.table("idxslope_analysis")
+        );
+        slopes.iter().try_for_each(|(k, v)| {
+            let key = format!("idx_{}_{}", k.itype.as_idx_str(), k.attr);
+            self.execute(&insert, &[&key, &i16::from(*v)])
+        })
+    }
+
+    pub fn is_idx_slopeyness_generated(&self) -> Result<bool, OperationError> {
+        self.exists_table("idxslope_analysis")
+    }
+
+    pub fn get_idx_slope(&self, ikey: &IdxKey) -> Result<Option<IdxSlope>, OperationError> {
+        if !self.exists_table("idxslope_analysis")? {
+            return Ok(None);
+        }
+
+        let key = format!("idx_{}_{}", ikey.itype.as_idx_str(), ikey.attr);
+
+        let slope: Option<i16> = self
+            .query_opt(
+                &format!(
+                    "SELECT slope FROM {} WHERE id = $1",
+                    self.table("idxslope_analysis")
+                ),
+                &[&key],
+            )?
+            .map(|row| row.try_get(0))
+            .transpose()
+            .map_err(postgres_error)?;
+        let slope = slope.and_then(|s| IdxSlope::try_from(s).ok());
+        trace!(name = %key, ?slope, "Got slope for index");
+
+        Ok(slope)
+    }
+
+    /// Move an entry between id2entry and the quarantine.
+    fn move_entry(&self, from: &str, to: &str, id: u64) -> Result<(), OperationError> {
+        let iid = i64::try_from(id).map_err(|_| OperationError::InvalidEntryId)?;
+
+        let row = self
+            .query_opt(
+                &format!(
+                    "DELETE FROM {} WHERE id = $1 RETURNING id, data",
+                    self.table(from)
+                ),
+                &[&iid],
+            )?
+            .ok_or(OperationError::InvalidEntryId)?;
+        let entry = row_to_raw_entry(&row)?;
+        let eid = from_entry_id(entry.id)?;
+
+        self.execute(
+            &format!(
+                "INSERT INTO {} (id, data) VALUES($1, $2)
+                 ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data",
+                self.table(to)
+            ),
+            &[&eid, &entry.data],
+        )
+    }
+
+    pub fn quarantine_entry(&self, id: u64) -> Result<(), OperationError> {
+        self.move_entry("id2entry", "id2entry_quarantine", id)
+    }
+
+    pub fn restore_quarantined(&self, id: u64) -> Result<(), OperationError> {
+        self.move_entry("id2entry_quarantine", "id2entry", id)
+    }
+
+    /// ⚠️  - This function will destroy all entries in the database.
+    ///
+    /// It should only be called internally by the backend in limited and
+    /// specific situations.
+    #[instrument(level = "trace", skip_all)]
+    pub fn danger_purge_id2entry(&self) -> Result<(), OperationError> {
+        self.execute(&format!("DELETE FROM {}", self.table("id2entry")), &[])
+    }
+
+    /// Write a value to one of the single row metadata tables.
+    fn set_db_meta<T: serde::Serialize>(
+        &self,
+        table: &str,
+        id: i64,
+        value: &T,
+    ) -> Result<(), OperationError> {
+        let data = serde_json::to_vec(value).map_err(serde_json_error)?;
+        self.execute(
+            &format!(
+                "INSERT INTO {} (id, data) VALUES($1, $2)
+                 ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data",
+                self.table(table)
+            ),
+            &[&id, &data],
+        )
+    }
+
+    pub fn write_db_s_uuid(&self, nsid: Uuid) -> Result<(), OperationError> {
+        self.set_db_meta("db_sid", 2, &nsid)
+    }
+
+    pub fn write_db_d_uuid(&self, nsid: Uuid) -> Result<(), OperationError> {
+        self.set_db_meta("db_did", 2, &nsid)
+    }
+
+    pub fn set_db_ts_max(&self, ts: Duration) -> Result<(), OperationError> {
+        self.set_db_meta("db_op_ts", 1, &ts)
+    }
+
+    fn get_db_version_key(&self, key: &str) -> Result<i64, OperationError> {
+        let version: Option<i64> = self
+            .query_opt(
+                &format!(
+                    "SELECT version FROM {} WHERE id = $1",
+                    self.table("db_version")
+                ),
+                &[&key],
+            )?
+            .map(|row| row.try_get(0))
+            .transpose()
+            .map_err(postgres_error)?;
+
+        // The value is missing, default to 0.
+        Ok(version.unwrap_or(0))
+    }
+
+    fn set_db_version_key(&self, key: &str, v: i64) -> Result<(), OperationError> {
+        self.execute(
+            &format!(
+                "INSERT INTO {} (id, version) VALUES($1, $2)
+                 ON CONFLICT (id) DO UPDATE SET version = EXCLUDED.version",
+                self.table("db_version")
+            ),
+            &[&key, &v],
+        )
+    }
+
+    pub(crate) fn get_db_index_version(&self) -> Result<i64, OperationError> {
+        self.get_db_version_key(DBV_INDEXV)
+    }
+
+    pub(crate) fn set_db_index_version(&self, v: i64) -> Result<(), OperationError> {
+        self.set_db_version_key(DBV_INDEXV, v)
+    }
+
+    pub(crate) fn get_key_handle(
+        &mut self,
+        handle: KeyHandleId,
+    ) -> Result<Option<KeyHandle>, OperationError> {
+        let s_handle = serde_json::to_vec(&handle).map_err(serde_json_error)?;
+
+        let data_raw: Option<Vec<u8>> = self
+            .query_opt(
+                &format!(
+                    "SELECT data FROM {} WHERE id = $1",
+                    self.table("keyhandles")
+                ),
+                &[&s_handle],
+            )?
+            .map(|row| row.try_get(0))
+            .transpose()
+            .map_err(postgres_error)?;
+
+        data_raw
+            .map(|d| serde_json::from_slice(d.as_slice()).map_err(serde_json_error))
+            .transpose()
+    }
+
+    #[instrument(level = "debug", skip(self, data))]
+    pub(crate) fn set_key_handle(
+        &mut self,
+        handle: KeyHandleId,
+        data: &KeyHandle,
+    ) -> Result<(), OperationError> {
+        let s_handle = serde_json::to_vec(&handle).map_err(serde_json_error)?;
+        let s_data = serde_json::to_vec(&data).map_err(serde_json_error)?;
+
+        self.execute(
+            &format!(
+                "INSERT INTO {} (id, data) VALUES($1, $2)
+                 ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data",
+                self.table("keyhandles")
+            ),
+            &[&s_handle, &s_data],
+        )
+    }
+
+    pub(super) fn set_key_handles(
+        &mut self,
+        keyhandles: &BTreeMap<KeyHandleId, KeyHandle>,
+    ) -> Result<(), OperationError> {
+        self.execute(&format!("DELETE FROM {}", self.table("keyhandles")), &[])?;
+
+        for (handle, data) in keyhandles {
+            self.set_key_handle(*handle, data)?;
+        }
+        Ok(())
+    }
+
+    /// PostgreSQL reclaims the space of deleted entries with autovacuum, and a vacuum can't
+    /// be run within the transaction that purged them, so there is nothing to report.
+    pub fn compact(&self) -> Result<DbCompaction, OperationError> {
+        error!("Compaction is not supported by PostgreSQL, which reclaims space with autovacuum");
+        Err(OperationError::DB0011StorageOptionUnsupported)
+    }
+
+    pub fn setup(&self) -> Result<(), OperationError> {
+        trace!(schema = %self.get_schema(), "setup");
+
+        self.execute(
+            &format!("CREATE SCHEMA IF NOT EXISTS \"{}\"", self.get_schema()),
+            &[],
+        )?;
+
+        // NEVER CHANGE THIS DEFINITION.
+        self.create_table("db_version", "id TEXT PRIMARY KEY, version BIGINT")?;
+
+        let mut dbv_id2entry = self.get_db_version_key(DBV_ID2ENTRY)?;
+
+        trace!(%dbv_id2entry);
+
+        // A new database is created with the layout of the latest SQLite version. Later changes
+        // to the layout are applied as migrations from this version onwards.
+        if dbv_id2entry == 0 {
+            self.create_table("id2entry", "id BIGINT PRIMARY KEY, data BYTEA NOT NULL")?;
+            self.create_table("db_sid", "id BIGINT PRIMARY KEY, data BYTEA NOT NULL")?;
+            self.create_table("db_did", "id BIGINT PRIMARY KEY, data BYTEA NOT NULL")?;
+            self.create_table("db_op_ts", "id BIGINT PRIMARY KEY, data BYTEA NOT NULL")?;
+            self.create_name2uuid()
+                .and_then(|_| self.create_uuid2spn())
+                .and_then(|_| self.create_uuid2rdn())
+                .and_then(|_| self.create_externalid2uuid())?;
+            self.create_table(
+                "id2entry_quarantine",
+                "id BIGINT PRIMARY KEY, data BYTEA NOT NULL",
+            )?;
+            self.create_table("keyhandles", "id BYTEA PRIMARY KEY, data BYTEA")?;
+            self.create_table("ruv", "cid TEXT PRIMARY KEY")?;
+
+            dbv_id2entry = DBV_ID2ENTRY_CURRENT;
+            info!(entry = %dbv_id2entry, "dbv_id2entry created");
+        }
+
+        if dbv_id2entry != DBV_ID2ENTRY_CURRENT {
+            error!(
+                ?dbv_id2entry,
+                "Unable to perform database migrations. This database version is unknown."
+            );
+            return Err(OperationError::InvalidDbState);
+        }
+
+        self.set_db_version_key(DBV_ID2ENTRY, dbv_id2entry)
+    }
+}
+
+impl IdlPostgres {
+    pub fn new(cfg: &BackendConfig, pg_cfg: &PostgresConfig) -> Result<Self, OperationError> {
+        let connector = PostgresConnector::new(pg_cfg)?;
+
+        let pool = Arc::new(Connections(Mutex::new(VecDeque::new())));
+
+        for i in 0..cfg.pool_size {
+            trace!("Opening Connection {}", i);
+            let conn = connector.connect().map_err(|e| {
+                error!(err = ?e, "Failed to build connection pool");
+                e
+            })?;
+            release_pooled(&pool, conn);
+        }
+
+        Ok(IdlPostgres {
+            pool,
+            connector: Arc::new(connector),
+            schema: cfg.db_name,
+            data_keys: Arc::new(OnceLock::new()),
+        })
+    }
+
+    /// Install the keys that sensitive values are encrypted with. This can only be done once,
+    /// as values written with these keys can't be read without them.
+    pub(crate) fn set_data_keys(
+        &self,
+        data_keys: DataEncryptionKeys,
+    ) -> Result<(), OperationError> {
+        self.data_keys.set(data_keys).map_err(|_| {
+            error!("Data encryption keys have already been installed");
+            OperationError::InvalidState
+        })
+    }
+
+    /// Take a connection from the pool, replacing it if it was closed.
+    fn take_conn(&self) -> Result<Client, OperationError> {
+        let conn = self
+            .pool
+            .lock()
+            .map_err(|e| {
+                error!(err = ?e, "Unable to lock connection pool.");
+                OperationError::BackendEngine
+            })?
+            .pop_front()
+            .ok_or_else(|| {
+                error!("Unable to retrieve connection from pool.");
+                OperationError::BackendEngine
+            })?;
+
+        if !conn.is_closed() {
+            return Ok(conn);
+        }
+
+        warn!("PostgreSQL connection was closed, reconnecting");
+        match self.connector.connect() {
+            Ok(new_conn) => {
+                close_conn(conn);
+                Ok(new_conn)
+            }
+            Err(e) => {
+                // Keep the closed connection, so the pool doesn't shrink.
+                release_pooled(&self.pool, conn);
+                Err(e)
+            }
+        }
+    }
+
+    pub(crate) fn get_allids_count(&self) -> Result<u64, OperationError> {
+        let mut conn = self.take_conn()?;
+        let query = format!("SELECT COUNT(id) FROM \"{}\".\"id2entry\"", self.schema);
+        let r = run_blocking(|| conn.query_one(query.as_str(), &[]))
+            .and_then(|row| row.try_get::<_, i64>(0))
+            .map_err(postgres_error)
+            .map(|c| u64::try_from(c).unwrap_or_default());
+        release_pooled(&self.pool, conn);
+        r
+    }
+
+    pub fn read(&self) -> Result<IdlPostgresReadTransaction, OperationError> {
+        let conn = self.take_conn()?;
+        IdlPostgresReadTransaction::new(
+            self.pool.clone(),
+            conn,
+            self.schema,
+            self.data_keys.clone(),
+        )
+    }
+
+    pub fn write(&self) -> Result<IdlPostgresWriteTransaction, OperationError> {
+        let conn = self.take_conn()?;
+        IdlPostgresWriteTransaction::new(
+            self.pool.clone(),
+            conn,
+            self.schema,
+            self.data_keys.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use idlset::v2::IDLBitRange;
+
+    use super::{IdlPostgres, IdlPostgresTransaction};
+    use crate::be::{BackendConfig, IdList, IdRawEntry, PostgresConfig};
+    use crate::prelude::*;
+    use crate::value::IndexType;
+
+    /// These tests need a database to connect to, such as
+    /// `postgres://postgres@localhost/kanidm_test`, and are skipped when it isn't set. Each test
+    /// uses its own schema, which is recreated when the test starts.
+    fn setup_test_postgres(db_name: &'static str) -> Option<IdlPostgres> {
+        let Ok(url) = std::env::var("KANIDM_TEST_POSTGRES_URL") else {
+            eprintln!("KANIDM_TEST_POSTGRES_URL is not set, skipping {db_name}");
+            return None;
+        };
+
+        sketching::test_init();
+        let mut cfg = BackendConfig::new_test(db_name);
+        cfg.pool_size = 2;
+        let pg_cfg = PostgresConfig {
+            url,
+            password: None,
+        };
+        let be = IdlPostgres::new(&cfg, &pg_cfg).unwrap();
+
+        let be_w = be.write().unwrap();
+        be_w.execute(&format!("DROP SCHEMA IF EXISTS \"{db_name}\" CASCADE"), &[])
+            .unwrap();
+        be_w.setup().unwrap();
+        be_w.commit().unwrap();
+
+        Some(be)
+    }
+
+    fn raw_entry(id: u64) -> IdRawEntry {
+        IdRawEntry {
+            id,
+            data: format!("entry {id}").into_bytes(),
+        }
+    }
+
+    fn all_ids(txn: &impl IdlPostgresTransaction) -> Vec<u64> {
+        txn.get_identry_raw(&IdList::AllIds)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_idl_postgres_verify() {
+        let Some(be) = setup_test_postgres("pg_verify") else {
+            return;
+        };
+        let be_w = be.write().unwrap();
+        let r = be_w.verify();
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn test_idl_postgres_id2entry() {
+        let Some(be) = setup_test_postgres("pg_id2entry") else {
+            return;
+        };
+
+        let be_w = be.write().unwrap();
+        be_w.write_identries_raw([raw_entry(1), raw_entry(2)].into_iter())
+            .unwrap();
+        // Writing an existing id replaces the entry.
+        be_w.write_identries_raw(
+            [IdRawEntry {
+                id: 2,
+                data: b"replaced".to_vec(),
+            }]
+            .into_iter(),
+        )
+        .unwrap();
+        assert_eq!(be_w.get_id2entry_max_id().unwrap(), 2);
+        be_w.commit().unwrap();
+
+        let be_r = be.read().unwrap();
+        let mut entries = be_r
+            .get_identry_raw(&IdList::Partial(IDLBitRange::from_iter([1, 2, 3])))
+            .unwrap();
+        entries.sort_by_key(|e| e.id);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].data, b"entry 1");
+        assert_eq!(entries[1].data, b"replaced");
+        drop(be_r);
+
+        let be_w = be.write().unwrap();
+        be_w.delete_identry(1).unwrap();
+        // Ids start at 1.
+        assert!(be_w.delete_identry(0).is_err());
+        be_w.commit().unwrap();
+
+        let be_r = be.read().unwrap();
+        assert_eq!(all_ids(&be_r), vec![2]);
+    }
+
+    #[test]
+    fn test_idl_postgres_idl() {
+        let Some(be) = setup_test_postgres("pg_idl") else {
+            return;
+        };
+
+        let be_w = be.write().unwrap();
+        // An index that doesn't exist is not the same as an empty one.
+        assert_eq!(
+            be_w.get_idl(&Attribute::Name, IndexType::Equality, "william")
+                .unwrap(),
+            None
+        );
+        be_w.create_idx(&Attribute::Name, IndexType::Equality)
+            .unwrap();
+
+        let idl = IDLBitRange::from_iter([1, 2, 64, 1024]);
+        be_w.write_idl(&Attribute::Name, IndexType::Equality, "william", &idl)
+            .unwrap();
+        be_w.commit().unwrap();
+
+        let be_r = be.read().unwrap();
+        assert_eq!(
+            be_r.get_idl(&Attribute::Name, IndexType::Equality, "william")
+                .unwrap(),
+            Some(idl)
+        );
+        assert_eq!(
+            be_r.get_idl(&Attribute::Name, IndexType::Equality, "claire")
+                .unwrap(),
+            Some(IDLBitRange::new())
+        );
+        drop(be_r);
+
+        // Writing an empty idl removes the key.
+        let be_w = be.write().unwrap();
+        be_w.write_idl(
+            &Attribute::Name,
+            IndexType::Equality,
+            "william",
+            &IDLBitRange::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            be_w.get_idl(&Attribute::Name, IndexType::Equality, "william")
+                .unwrap(),
+            Some(IDLBitRange::new())
+        );
+        be_w.commit().unwrap();
+    }
+
+    #[test]
+    fn test_idl_postgres_write_lock() {
+        let Some(be) = setup_test_postgres("pg_write_lock") else {
+            return;
+        };
+        // A second server that was wrongly configured with the same database.
+        let Some(other) = setup_test_postgres("pg_write_lock") else {
+            return;
+        };
+
+        let be_w = be.write().unwrap();
+        be_w.write_identries_raw([raw_entry(1)].into_iter())
+            .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let other_w = other.write().unwrap();
+            // The writer that waited sees everything that was committed before it.
+            let max_id = other_w.get_id2entry_max_id().unwrap();
+            tx.send(max_id).unwrap();
+            other_w.commit().unwrap();
+        });
+
+        // The other writer is held until this one finishes.
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+        be_w.commit().unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap(), 1);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_idl_postgres_read_snapshot() {
+        let Some(be) = setup_test_postgres("pg_read_snapshot") else {
+            return;
+        };
+
+        let be_w = be.write().unwrap();
+        be_w.write_identries_raw([raw_entry(1)].into_iter())
+            .unwrap();
+        be_w.commit().unwrap();
+
+        let be_r = be.read().unwrap();
+        assert_eq!(all_ids(&be_r), vec![1]);
+
+        let be_w = be.write().unwrap();
+        be_w.write_identries_raw([raw_entry(2)].into_iter())
+            .unwrap();
+        be_w.delete_identry(1).unwrap();
+        be_w.commit().unwrap();
+
+        // The reader still sees the database as it was when it started.
+        assert_eq!(all_ids(&be_r), vec![1]);
+        drop(be_r);
+
+        let be_r = be.read().unwrap();
+        assert_eq!(all_ids(&be_r), vec![2]);
+    }
+}
@@ -1,7 +1,6 @@
 use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
-use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
 use super::keystorage::{KeyHandle, KeyHandleId};
@@ -16,8 +15,8 @@ use uuid::Uuid;
 
 use crate::be::dbentry::DbIdentSpn;
 use crate::be::dbvalue::DbCidV1;
-use crate::be::{BackendConfig, IdList, IdRawEntry, IdxKey, IdxSlope};
-use crate::entry::{Entry, EntryCommitted, EntrySealed};
+use crate::be::encryption::DataEncryptionKeys;
+use crate::be::{BackendConfig, DbCompaction, IdList, IdRawEntry, IdxKey, IdxSlope};
 use crate::prelude::*;
 use crate::value::{IndexType, Value};
 
@@ -39,6 +38,7 @@ pub(super) fn serde_json_error(e: serde_json::Error) -> OperationError {
 }
 
 type ConnPool = Arc<Mutex<VecDeque<Connection>>>;
+type DataKeys = Arc<OnceLock<DataEncryptionKeys>>;
 
 #[derive(Debug)]
 pub struct IdSqliteEntry {
@@ -90,18 +90,21 @@ impl TryFrom<IdRawEntry> for IdSqliteEntry {
 pub struct IdlSqlite {
     pool: ConnPool,
     db_name: &'static str,
+    data_keys: DataKeys,
 }
 
 pub struct IdlSqliteReadTransaction {
     pool: ConnPool,
     conn: Option<Connection>,
     db_name: &'static str,
+    data_keys: DataKeys,
 }
 
 pub struct IdlSqliteWriteTransaction {
     pool: ConnPool,
     conn: Option<Connection>,
     db_name: &'static str,
+    data_keys: DataKeys,
 }
 
 pub(crate) trait IdlSqliteTransaction {
@@ -109,12 +112,7 @@ pub(crate) trait IdlSqliteTransaction {
 
     fn get_conn(&self) -> Result<&Connection, OperationError>;
 
-    fn get_identry(&self, idl: &IdList) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError> {
-        self.get_identry_raw(idl)?
-            .into_iter()
-            .map(|ide| ide.into_entry().map(Arc::new))
-            .collect()
-    }
+    fn get_data_keys(&self) -> Option<&DataEncryptionKeys>;
 
     fn get_identry_raw(&self, idl: &IdList) -> Result<Vec<IdRawEntry>, OperationError> {
         // is the idl allids?
@@ -532,14 +530,6 @@ pub(crate) trait IdlSqliteTransaction {
         idx_table_iter.map(|v| v.map_err(sqlite_error)).collect()
     }
 
-    fn list_id2entry(&self) -> Result<Vec<(u64, String)>, OperationError> {
-        let allids = self.get_identry_raw(&IdList::AllIds)?;
-        allids
-            .into_iter()
-            .map(|data| data.into_dbentry().map(|(id, db_e)| (id, db_e.to_string())))
-            .collect()
-    }
-
     fn list_quarantined(&self) -> Result<Vec<(u64, String)>, OperationError> {
         // This is a more direct version of get_identry_raw adapted for the simpler
         // quarantine setup.
@@ -573,18 +563,6 @@ pub(crate) trait IdlSqliteTransaction {
             .collect()
     }
 
-    fn get_id2entry(&self, id: u64) -> Result<(u64, String), OperationError> {
-        let idl = IdList::Indexed(IDLBitRange::from_u64(id));
-        let mut allids = self.get_identry_raw(&idl)?;
-        allids
-            .pop()
-            .ok_or(OperationError::InvalidEntryId)
-            .and_then(|data| {
-                data.into_dbentry()
-                    .map(|(id, db_e)| (id, format!("{db_e:?}")))
-            })
-    }
-
     fn list_index_content(
         &self,
         index_name: &str,
@@ -656,6 +634,10 @@ impl IdlSqliteTransaction for IdlSqliteReadTransaction {
             .as_ref()
             .ok_or(OperationError::TransactionAlreadyCommitted)
     }
+
+    fn get_data_keys(&self) -> Option<&DataEncryptionKeys> {
+        self.data_keys.get()
+    }
 }
 
 impl Drop for IdlSqliteReadTransaction {
@@ -683,6 +665,7 @@ impl IdlSqliteReadTransaction {
         pool: ConnPool,
         conn: Connection,
         db_name: &'static str,
+        data_keys: DataKeys,
     ) -> Result<Self, OperationError> {
         // Start the transaction
         //
@@ -698,6 +681,7 @@ impl IdlSqliteReadTransaction {
             pool,
             conn: Some(conn),
             db_name,
+            data_keys,
         })
     }
 }
@@ -712,6 +696,10 @@ impl IdlSqliteTransaction for IdlSqliteWriteTransaction {
             .as_ref()
             .ok_or(OperationError::TransactionAlreadyCommitted)
     }
+
+    fn get_data_keys(&self) -> Option<&DataEncryptionKeys> {
+        self.data_keys.get()
+    }
 }
 
 impl Drop for IdlSqliteWriteTransaction {
@@ -739,6 +727,7 @@ impl IdlSqliteWriteTransaction {
         pool: ConnPool,
         conn: Connection,
         db_name: &'static str,
+        data_keys: DataKeys,
     ) -> Result<Self, OperationError> {
         // Start the transaction
         conn.execute("BEGIN EXCLUSIVE TRANSACTION", [])
@@ -747,6 +736,7 @@ impl IdlSqliteWriteTransaction {
             pool,
             conn: Some(conn),
             db_name,
+            data_keys,
         })
     }
 
@@ -803,21 +793,6 @@ impl IdlSqliteWriteTransaction {
         }
     }
 
-    pub fn write_identry(
-        &self,
-        entry: &Entry<EntrySealed, EntryCommitted>,
-    ) -> Result<(), OperationError> {
-        let dbe = entry.to_dbentry();
-        let data = serde_json::to_vec(&dbe).map_err(serde_json_error)?;
-
-        let raw_entries = std::iter::once(IdRawEntry {
-            id: entry.get_id(),
-            data,
-        });
-
-        self.write_identries_raw(raw_entries)
-    }
-
     pub fn write_identries_raw<I>(&self, mut entries: I) -> Result<(), OperationError>
     where
         I: Iterator<Item = IdRawEntry>,
@@ -1520,6 +1495,42 @@ impl IdlSqliteWriteTransaction {
         self.set_db_version_key(DBV_INDEXV, v)
     }
 
+    fn get_pragma_value(&self, pragma: &str) -> Result<u64, OperationError> {
+        self.get_conn()?
+            .query_row(
+                &format!("PRAGMA {}.{}", self.get_db_name(), pragma),
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|v| u64::try_from(v).unwrap_or_default())
+            .map_err(sqlite_error)
+    }
+
+    /// Return the pages freed by deleted entries to the filesystem. This is only possible when
+    /// the database is in incremental auto vacuum mode, which is enabled when the database is
+    /// created or vacuumed. Otherwise the free pages are only reported.
+    pub fn compact(&self) -> Result<DbCompaction, OperationError> {
+        let page_size = self.get_pragma_value("page_size")?;
+        let free_before = self.get_pragma_value("freelist_count")?;
+
+        // 2 is INCREMENTAL.
+        if free_before > 0 && self.get_pragma_value("auto_vacuum")? == 2 {
+            self.get_conn()?
+                .execute_batch(&format!(
+                    "PRAGMA {}.incremental_vacuum;",
+                    self.get_db_name()
+                ))
+                .map_err(sqlite_error)?;
+        }
+
+        let free_after = self.get_pragma_value("freelist_count")?;
+
+        Ok(DbCompaction {
+            reclaimed: free_before.saturating_sub(free_after) * page_size,
+            free: free_after * page_size,
+        })
+    }
+
     pub fn setup(&self) -> Result<(), OperationError> {
         // If the db_name is NOT main, we MAY need to create it as we are in
         // a test!
@@ -1718,7 +1729,9 @@ impl IdlSqlite {
         // If provided, set the page size to match the tuning we want. By default we use 4096. The VACUUM
         // immediately after is so that on db create the page size takes effect.
         //
-        // Enable WAL mode, which is just faster and better for our needs.
+        // Enable WAL mode, which is just faster and better for our needs. Incremental auto vacuum
+        // only takes effect on a new database or after a vacuum, and allows pages freed by purges
+        // to be returned to the filesystem while online.
         let mut flags = OpenFlags::default();
         // Open with multi thread flags and locking options.
 
@@ -1741,6 +1754,7 @@ impl IdlSqlite {
                 .execute_batch(
                     format!(
                         "PRAGMA page_size={fs_page_size};
+                         PRAGMA auto_vacuum=INCREMENTAL;
                          PRAGMA cache_size={cache_pages};
                          PRAGMA journal_mode=WAL;
                          PRAGMA wal_autocheckpoint={checkpoint_pages};
@@ -1796,6 +1810,13 @@ impl IdlSqlite {
                     OperationError::SqliteError
                 })?;
 
+            vconn
+                .pragma_update(None, "auto_vacuum", "INCREMENTAL")
+                .map_err(|e| {
+                    admin_error!(?e, "rusqlite auto_vacuum update error");
+                    OperationError::SqliteError
+                })?;
+
             vconn.execute_batch("VACUUM").map_err(|e| {
                 admin_error!(?e, "rusqlite vacuum error");
                 OperationError::SqliteError
@@ -1864,6 +1885,19 @@ impl IdlSqlite {
         Ok(IdlSqlite {
             pool,
             db_name: cfg.db_name,
+            data_keys: Arc::new(OnceLock::new()),
+        })
+    }
+
+    /// Install the keys that sensitive values are encrypted with. This can only be done once,
+    /// as values written with these keys can't be read without them.
+    pub(crate) fn set_data_keys(
+        &self,
+        data_keys: DataEncryptionKeys,
+    ) -> Result<(), OperationError> {
+        self.data_keys.set(data_keys).map_err(|_| {
+            error!("Data encryption keys have already been installed");
+            OperationError::InvalidState
         })
     }
 
@@ -1894,7 +1928,12 @@ impl IdlSqlite {
             OperationError::BackendEngine
         })?;
 
-        IdlSqliteReadTransaction::new(self.pool.clone(), conn, self.db_name)
+        IdlSqliteReadTransaction::new(
+            self.pool.clone(),
+            conn,
+            self.db_name,
+            self.data_keys.clone(),
+        )
     }
 
     pub fn write(&self) -> Result<IdlSqliteWriteTransaction, OperationError> {
@@ -1909,7 +1948,12 @@ impl IdlSqlite {
             OperationError::BackendEngine
         })?;
 
-        IdlSqliteWriteTransaction::new(self.pool.clone(), conn, self.db_name)
+        IdlSqliteWriteTransaction::new(
+            self.pool.clone(),
+            conn,
+            self.db_name,
+            self.data_keys.clone(),
+        )
     }
 }
 
@@ -0,0 +1,536 @@
+//! The storage layer of the backend, which persists entries, indexes and the metadata of the
+//! database. SQLite is the default, and is embedded in the server. PostgreSQL can be used
+//! instead, so that the database is held by an external service.
+//!
+//! The caches of `idl_arc_sqlite` only access storage through the traits of this module, so
+//! that they don't need to know which option is configured. Values are encrypted and
+//! decrypted here as well, so that every option encrypts the same values in the same way.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hashbrown::HashMap;
+use idlset::v2::IDLBitRange;
+use kanidm_proto::internal::{ConsistencyError, OperationError};
+use uuid::Uuid;
+
+use crate::be::encryption::DataEncryptionKeys;
+use crate::be::idl_postgres::{
+    IdlPostgres, IdlPostgresReadTransaction, IdlPostgresTransaction, IdlPostgresWriteTransaction,
+};
+use crate::be::idl_sqlite::{
+    serde_json_error, IdlSqlite, IdlSqliteReadTransaction, IdlSqliteTransaction,
+    IdlSqliteWriteTransaction,
+};
+use crate::be::keystorage::{KeyHandle, KeyHandleId};
+use crate::be::{BackendConfig, DbCompaction, IdList, IdRawEntry, IdxKey, IdxSlope};
+use crate::entry::{Entry, EntryCommitted, EntrySealed};
+use crate::prelude::*;
+use crate::value::{IndexType, Value};
+
+/// Call the same method on whichever storage option this is.
+macro_rules! dispatch {
+    ($self:expr, $txn:ident => $call:expr) => {
+        match $self {
+            Self::Sqlite($txn) => $call,
+            Self::Postgres($txn) => $call,
+        }
+    };
+}
+
+pub(crate) trait IdlStorageTransaction {
+    fn get_data_keys(&self) -> Option<&DataEncryptionKeys>;
+
+    fn get_identry(&self, idl: &IdList) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError> {
+        self.get_identry_raw(idl)?
+            .into_iter()
+            .map(|ide| ide.into_entry(self.get_data_keys()).map(Arc::new))
+            .collect()
+    }
+
+    fn get_identry_raw(&self, idl: &IdList) -> Result<Vec<IdRawEntry>, OperationError>;
+
+    fn get_idl(
+        &self,
+        attr: &Attribute,
+        itype: IndexType,
+        idx_key: &str,
+    ) -> Result<Option<IDLBitRange>, OperationError>;
+
+    fn name2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError>;
+
+    fn externalid2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError>;
+
+    fn uuid2spn(&mut self, uuid: Uuid) -> Result<Option<Value>, OperationError>;
+
+    fn uuid2rdn(&mut self, uuid: Uuid) -> Result<Option<String>, OperationError>;
+
+    fn get_db_s_uuid(&self) -> Result<Option<Uuid>, OperationError>;
+
+    fn get_db_d_uuid(&self) -> Result<Option<Uuid>, OperationError>;
+
+    fn get_db_ts_max(&self) -> Result<Option<Duration>, OperationError>;
+
+    fn get_key_handles(&mut self) -> Result<BTreeMap<KeyHandleId, KeyHandle>, OperationError>;
+
+    fn get_allids(&self) -> Result<IDLBitRange, OperationError>;
+
+    fn list_idxs(&self) -> Result<Vec<String>, OperationError>;
+
+    fn list_id2entry(&self) -> Result<Vec<(u64, String)>, OperationError> {
+        let allids = self.get_identry_raw(&IdList::AllIds)?;
+        allids
+            .into_iter()
+            .map(|data| data.into_dbentry().map(|(id, db_e)| (id, db_e.to_string())))
+            .collect()
+    }
+
+    fn list_quarantined(&self) -> Result<Vec<(u64, String)>, OperationError>;
+
+    fn get_id2entry(&self, id: u64) -> Result<(u64, String), OperationError> {
+        let idl = IdList::Indexed(IDLBitRange::from_u64(id));
+        let mut allids = self.get_identry_raw(&idl)?;
+        allids
+            .pop()
+            .ok_or(OperationError::InvalidEntryId)
+            .and_then(|data| {
+                data.into_dbentry()
+                    .map(|(id, db_e)| (id, format!("{db_e:?}")))
+            })
+    }
+
+    fn list_index_content(
+        &self,
+        index_name: &str,
+    ) -> Result<Vec<(String, IDLBitRange)>, OperationError>;
+
+    fn verify(&self) -> Vec<Result<(), ConsistencyError>>;
+}
+
+pub(crate) trait IdlStorageWriteTransaction: IdlStorageTransaction {
+    fn commit(self) -> Result<(), OperationError>;
+
+    fn get_id2entry_max_id(&self) -> Result<u64, OperationError>;
+
+    fn write_identry(
+        &self,
+        entry: &Entry<EntrySealed, EntryCommitted>,
+    ) -> Result<(), OperationError> {
+        let mut dbe = entry.to_dbentry();
+        if let Some(data_keys) = self.get_data_keys() {
+            data_keys.seal_dbentry(&mut dbe)?;
+        }
+        let data = serde_json::to_vec(&dbe).map_err(serde_json_error)?;
+
+        let raw_entries = std::iter::once(IdRawEntry {
+            id: entry.get_id(),
+            data,
+        });
+
+        self.write_identries_raw(raw_entries)
+    }
+
+    fn write_identries_raw<I>(&self, entries: I) -> Result<(), OperationError>
+    where
+        I: Iterator<Item = IdRawEntry>;
+
+    fn delete_identry(&self, id: u64) -> Result<(), OperationError>;
+
+    fn write_idl(
+        &self,
+        attr: &Attribute,
+        itype: IndexType,
+        idx_key: &str,
+        idl: &IDLBitRange,
+    ) -> Result<(), OperationError>;
+
+    fn create_name2uuid(&self) -> Result<(), OperationError>;
+
+    fn write_name2uuid_add(&self, name: &str, uuid: Uuid) -> Result<(), OperationError>;
+
+    fn write_name2uuid_rem(&self, name: &str) -> Result<(), OperationError>;
+
+    fn create_externalid2uuid(&self) -> Result<(), OperationError>;
+
+    fn write_externalid2uuid_add(&self, name: &str, uuid: Uuid) -> Result<(), OperationError>;
+
+    fn write_externalid2uuid_rem(&self, name: &str) -> Result<(), OperationError>;
+
+    fn create_uuid2spn(&self) -> Result<(), OperationError>;
+
+    fn write_uuid2spn(&self, uuid: Uuid, k: Option<&Value>) -> Result<(), OperationError>;
+
+    fn create_uuid2rdn(&self) -> Result<(), OperationError>;
+
+    fn write_uuid2rdn(&self, uuid: Uuid, k: Option<&String>) -> Result<(), OperationError>;
+
+    fn get_db_ruv(&self) -> Result<BTreeSet<Cid>, OperationError>;
+
+    fn write_db_ruv<I, J>(&mut self, added: I, removed: J) -> Result<(), OperationError>
+    where
+        I: Iterator<Item = Cid>,
+        J: Iterator<Item = Cid>;
+
+    fn create_idx(&self, attr: &Attribute, itype: IndexType) -> Result<(), OperationError>;
+
+    /// ⚠️  - This function will destroy all indexes in the database.
+    ///
+    /// It should only be called internally by the backend in limited and
+    /// specific situations.
+    fn danger_purge_idxs(&self) -> Result<(), OperationError>;
+
+    fn store_idx_slope_analysis(
+        &self,
+        slopes: &HashMap<IdxKey, IdxSlope>,
+    ) -> Result<(), OperationError>;
+
+    fn is_idx_slopeyness_generated(&self) -> Result<bool, OperationError>;
+
+    fn get_idx_slope(&self, ikey: &IdxKey) -> Result<Option<IdxSlope>, OperationError>;
+
+    fn quarantine_entry(&self, id: u64) -> Result<(), OperationError>;
+
+    fn restore_quarantined(&self, id: u64) -> Result<(), OperationError>;
+
+    /// ⚠️  - This function will destroy all entries in the database.
+    ///
+    /// It should only be called internally by the backend in limited and
+    /// specific situations.
+    fn danger_purge_id2entry(&self) -> Result<(), OperationError>;
+
+    fn write_db_s_uuid(&self, nsid: Uuid) -> Result<(), OperationError>;
+
+    fn write_db_d_uuid(&self, nsid: Uuid) -> Result<(), OperationError>;
+
+    fn set_db_ts_max(&self, ts: Duration) -> Result<(), OperationError>;
+
+    fn get_db_index_version(&self) -> Result<i64, OperationError>;
+
+    fn set_db_index_version(&self, v: i64) -> Result<(), OperationError>;
+
+    fn get_key_handle(&mut self, handle: KeyHandleId) -> Result<Option<KeyHandle>, OperationError>;
+
+    fn set_key_handle(
+        &mut self,
+        handle: KeyHandleId,
+        data: &KeyHandle,
+    ) -> Result<(), OperationError>;
+
+    fn set_key_handles(
+        &mut self,
+        keyhandles: &BTreeMap<KeyHandleId, KeyHandle>,
+    ) -> Result<(), OperationError>;
+
+    fn compact(&self) -> Result<DbCompaction, OperationError>;
+
+    fn setup(&self) -> Result<(), OperationError>;
+}
+
+/// The storage option that the database is kept in.
+#[derive(Clone)]
+pub(crate) enum IdlStorage {
+    Sqlite(IdlSqlite),
+    Postgres(IdlPostgres),
+}
+
+pub(crate) enum IdlStorageRead {
+    Sqlite(IdlSqliteReadTransaction),
+    Postgres(IdlPostgresReadTransaction),
+}
+
+pub(crate) enum IdlStorageWrite {
+    Sqlite(IdlSqliteWriteTransaction),
+    Postgres(IdlPostgresWriteTransaction),
+}
+
+impl IdlStorage {
+    pub fn new(cfg: &BackendConfig, vacuum: bool) -> Result<Self, OperationError> {
+        match &cfg.postgres {
+            Some(pg_cfg) => {
+                if vacuum {
+                    error!("Vacuum is only supported when the database is stored in SQLite");
+                    return Err(OperationError::DB0011StorageOptionUnsupported);
+                }
+                IdlPostgres::new(cfg, pg_cfg).map(IdlStorage::Postgres)
+            }
+            None => IdlSqlite::new(cfg, vacuum).map(IdlStorage::Sqlite),
+        }
+    }
+
+    /// Install the keys that sensitive values are encrypted with. This can only be done once,
+    /// as values written with these keys can't be read without them.
+    pub(crate) fn set_data_keys(
+        &self,
+        data_keys: DataEncryptionKeys,
+    ) -> Result<(), OperationError> {
+        dispatch!(self, db => db.set_data_keys(data_keys))
+    }
+
+    pub(crate) fn get_allids_count(&self) -> Result<u64, OperationError> {
+        dispatch!(self, db => db.get_allids_count())
+    }
+
+    pub fn read(&self) -> Result<IdlStorageRead, OperationError> {
+        match self {
+            Self::Sqlite(db) => db.read().map(IdlStorageRead::Sqlite),
+            Self::Postgres(db) => db.read().map(IdlStorageRead::Postgres),
+        }
+    }
+
+    pub fn write(&self) -> Result<IdlStorageWrite, OperationError> {
+        match self {
+            Self::Sqlite(db) => db.write().map(IdlStorageWrite::Sqlite),
+            Self::Postgres(db) => db.write().map(IdlStorageWrite::Postgres),
+        }
+    }
+}
+
+/// The read methods are the same for read and write transactions.
+macro_rules! impl_idl_storage_transaction {
+    ($txn_type:ty) => {
+        impl IdlStorageTransaction for $txn_type {
+            fn get_data_keys(&self) -> Option<&DataEncryptionKeys> {
+                dispatch!(self, txn => txn.get_data_keys())
+            }
+
+            fn get_identry_raw(&self, idl: &IdList) -> Result<Vec<IdRawEntry>, OperationError> {
+                dispatch!(self, txn => txn.get_identry_raw(idl))
+            }
+
+            fn get_idl(
+                &self,
+                attr: &Attribute,
+                itype: IndexType,
+                idx_key: &str,
+            ) -> Result<Option<IDLBitRange>, OperationError> {
+                dispatch!(self, txn => txn.get_idl(attr, itype, idx_key))
+            }
+
+            fn name2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError> {
+                dispatch!(self, txn => txn.name2uuid(name))
+            }
+
+            fn externalid2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError> {
+                dispatch!(self, txn => txn.externalid2uuid(name))
+            }
+
+            fn uuid2spn(&mut self, uuid: Uuid) -> Result<Option<Value>, OperationError> {
+                dispatch!(self, txn => txn.uuid2spn(uuid))
+            }
+
+            fn uuid2rdn(&mut self, uuid: Uuid) -> Result<Option<String>, OperationError> {
+                dispatch!(self, txn => txn.uuid2rdn(uuid))
+            }
+
+            fn get_db_s_uuid(&self) -> Result<Option<Uuid>, OperationError> {
+                dispatch!(self, txn => txn.get_db_s_uuid())
+            }
+
+            fn get_db_d_uuid(&self) -> Result<Option<Uuid>, OperationError> {
+                dispatch!(self, txn => txn.get_db_d_uuid())
+            }
+
+            fn get_db_ts_max(&self) -> Result<Option<Duration>, OperationError> {
+                dispatch!(self, txn => txn.get_db_ts_max())
+            }
+
+            fn get_key_handles(
+                &mut self,
+            ) -> Result<BTreeMap<KeyHandleId, KeyHandle>, OperationError> {
+                dispatch!(self, txn => txn.get_key_handles())
+            }
+
+            fn get_allids(&self) -> Result<IDLBitRange, OperationError> {
+                dispatch!(self, txn => txn.get_allids())
+            }
+
+            fn list_idxs(&self) -> Result<Vec<String>, OperationError> {
+                dispatch!(self, txn => txn.list_idxs())
+            }
+
+            fn list_quarantined(&self) -> Result<Vec<(u64, String)>, OperationError> {
+                dispatch!(self, txn => txn.list_quarantined())
+            }
+
+            fn list_index_content(
+                &self,
+                index_name: &str,
+            ) -> Result<Vec<(String, IDLBitRange)>, OperationError> {
+                dispatch!(self, txn => txn.list_index_content(index_name))
+            }
+
+            fn verify(&self) -> Vec<Result<(), ConsistencyError>> {
+                dispatch!(self, txn => txn.verify())
+            }
+        }
+    };
+}
+
+impl_idl_storage_transaction!(IdlStorageRead);
+impl_idl_storage_transaction!(IdlStorageWrite);
+
+impl IdlStorageWriteTransaction for IdlStorageWrite {
+    fn commit(self) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.commit())
+    }
+
+    fn get_id2entry_max_id(&self) -> Result<u64, OperationError> {
+        dispatch!(self, txn => txn.get_id2entry_max_id())
+    }
+
+    fn write_identries_raw<I>(&self, entries: I) -> Result<(), OperationError>
+    where
+        I: Iterator<Item = IdRawEntry>,
+    {
+        dispatch!(self, txn => txn.write_identries_raw(entries))
+    }
+
+    fn delete_identry(&self, id: u64) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.delete_identry(id))
+    }
+
+    fn write_idl(
+        &self,
+        attr: &Attribute,
+        itype: IndexType,
+        idx_key: &str,
+        idl: &IDLBitRange,
+    ) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.write_idl(attr, itype, idx_key, idl))
+    }
+
+    fn create_name2uuid(&self) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.create_name2uuid())
+    }
+
+    fn write_name2uuid_add(&self, name: &str, uuid: Uuid) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.write_name2uuid_add(name, uuid))
+    }
+
+    fn write_name2uuid_rem(&self, name: &str) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.write_name2uuid_rem(name))
+    }
+
+    fn create_externalid2uuid(&self) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.create_externalid2uuid())
+    }
+
+    fn write_externalid2uuid_add(&self, name: &str, uuid: Uuid) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.write_externalid2uuid_add(name, uuid))
+    }
+
+    fn write_externalid2uuid_rem(&self, name: &str) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.write_externalid2uuid_rem(name))
+    }
+
+    fn create_uuid2spn(&self) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.create_uuid2spn())
+    }
+
+    fn write_uuid2spn(&self, uuid: Uuid, k: Option<&Value>) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.write_uuid2spn(uuid, k))
+    }
+
+    fn create_uuid2rdn(&self) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.create_uuid2rdn())
+    }
+
+    fn write_uuid2rdn(&self, uuid: Uuid, k: Option<&String>) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.write_uuid2rdn(uuid, k))
+    }
+
+    fn get_db_ruv(&self) -> Result<BTreeSet<Cid>, OperationError> {
+        dispatch!(self, txn => txn.get_db_ruv())
+    }
+
+    fn write_db_ruv<I, J>(&mut self, added: I, removed: J) -> Result<(), OperationError>
+    where
+        I: Iterator<Item = Cid>,
+        J: Iterator<Item = Cid>,
+    {
+        dispatch!(self, txn => txn.write_db_ruv(added, removed))
+    }
+
+    fn create_idx(&self, attr: &Attribute, itype: IndexType) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.create_idx(attr, itype))
+    }
+
+    fn danger_purge_idxs(&self) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.danger_purge_idxs())
+    }
+
+    fn store_idx_slope_analysis(
+        &self,
+        slopes: &HashMap<IdxKey, IdxSlope>,
+    ) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.store_idx_slope_analysis(slopes))
+    }
+
+    fn is_idx_slopeyness_generated(&self) -> Result<bool, OperationError> {
+        dispatch!(self, txn => txn.is_idx_slopeyness_generated())
+    }
+
+    fn get_idx_slope(&self, ikey: &IdxKey) -> Result<Option<IdxSlope>, OperationError> {
+        dispatch!(self, txn => txn.get_idx_slope(ikey))
+    }
+
+    fn quarantine_entry(&self, id: u64) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.quarantine_entry(id))
+    }
+
+    fn restore_quarantined(&self, id: u64) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.restore_quarantined(id))
+    }
+
+    fn danger_purge_id2entry(&self) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.danger_purge_id2entry())
+    }
+
+    fn write_db_s_uuid(&self, nsid: Uuid) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.write_db_s_uuid(nsid))
+    }
+
+    fn write_db_d_uuid(&self, nsid: Uuid) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.write_db_d_uuid(nsid))
+    }
+
+    fn set_db_ts_max(&self, ts: Duration) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.set_db_ts_max(ts))
+    }
+
+    fn get_db_index_version(&self) -> Result<i64, OperationError> {
+        dispatch!(self, txn => txn.get_db_index_version())
+    }
+
+    fn set_db_index_version(&self, v: i64) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.set_db_index_version(v))
+    }
+
+    fn get_key_handle(&mut self, handle: KeyHandleId) -> Result<Option<KeyHandle>, OperationError> {
+        dispatch!(self, txn => txn.get_key_handle(handle))
+    }
+
+    fn set_key_handle(
+        &mut self,
+        handle: KeyHandleId,
+        data: &KeyHandle,
+    ) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.set_key_handle(handle, data))
+    }
+
+    fn set_key_handles(
+        &mut self,
+        keyhandles: &BTreeMap<KeyHandleId, KeyHandle>,
+    ) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.set_key_handles(keyhandles))
+    }
+
+    fn compact(&self) -> Result<DbCompaction, OperationError> {
+        dispatch!(self, txn => txn.compact())
+    }
+
+    fn setup(&self) -> Result<(), OperationError> {
+        dispatch!(self, txn => txn.setup())
+    }
+}
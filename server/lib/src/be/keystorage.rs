@@ -4,11 +4,13 @@ use kanidm_lib_crypto::serialise::{pkeyb64, x509b64};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::hash::Hash;
+use uuid::Uuid;
 
 use super::idl_arc_sqlite::IdlArcSqliteWriteTransaction;
 use super::idl_sqlite::IdlSqliteTransaction;
 use super::idl_sqlite::IdlSqliteWriteTransaction;
 use super::idl_sqlite::{serde_json_error, sqlite_error};
+use super::idl_storage::IdlStorageWriteTransaction;
 use super::BackendWriteTransaction;
 use crate::prelude::OperationError;
 
@@ -20,6 +22,10 @@ use crate::prelude::OperationError;
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub enum KeyHandleId {
     ReplicationKey,
+    RadiusCertificateAuthority,
+    DataEncryptionKeys,
+    AcmeAccount,
+    AcmeCertificate,
 }
 
 /// This is a key handle that contains the actual data that is persisted in the DB.
@@ -31,6 +37,24 @@ pub enum KeyHandle {
         #[serde(with = "x509b64")]
         x509: X509,
     },
+    /// The keys that encrypt sensitive values at rest, each wrapped by the key encryption
+    /// key that is held outside of the database.
+    DataEncryptionKeys {
+        active: Uuid,
+        wrapped: BTreeMap<Uuid, Vec<u8>>,
+    },
+    /// The key of an ACME account, and the url of the account once it is registered.
+    AcmeAccount {
+        #[serde(with = "pkeyb64")]
+        private: PKey<Private>,
+        url: Option<String>,
+    },
+    /// A private key and its certificate chain, leaf certificate first, in DER.
+    X509Chain {
+        #[serde(with = "pkeyb64")]
+        private: PKey<Private>,
+        chain: Vec<Vec<u8>>,
+    },
 }
 
 impl BackendWriteTransaction<'_> {
@@ -8,7 +8,7 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::ops::DerefMut;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use concread::cowcell::*;
 use hashbrown::{HashMap as Map, HashSet};
@@ -36,18 +36,29 @@ pub(crate) mod dbentry;
 pub(crate) mod dbrepl;
 pub(crate) mod dbvalue;
 
+mod encryption;
 mod idl_arc_sqlite;
+mod idl_postgres;
 mod idl_sqlite;
+mod idl_storage;
 pub(crate) mod idxkey;
 pub(crate) mod keystorage;
+pub(crate) mod restore;
+mod stats;
 
 pub(crate) use self::idxkey::{IdxKey, IdxKeyRef, IdxKeyToRef, IdxSlope};
+use crate::be::encryption::{unseal_dbentry, DataEncryptionKeys};
 use crate::be::idl_arc_sqlite::{
     IdlArcSqlite, IdlArcSqliteReadTransaction, IdlArcSqliteTransaction,
     IdlArcSqliteWriteTransaction,
 };
+use crate::be::restore::parse_backup;
+use crate::be::stats::{QueryStats, SearchStats};
+use crate::metrics::ServerMetrics;
 use kanidm_proto::internal::FsType;
 
+pub use crate::be::encryption::KeyEncryptionKey;
+
 // Currently disabled due to improvements in idlset for intersection handling.
 const FILTER_SEARCH_TEST_THRESHOLD: usize = 0;
 const FILTER_EXISTS_TEST_THRESHOLD: usize = 0;
@@ -119,6 +130,16 @@ pub struct IdRawEntry {
     data: Vec<u8>,
 }
 
+/// The free space of the database after a compaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DbCompaction {
+    /// The bytes that were returned to the filesystem.
+    pub reclaimed: u64,
+    /// The bytes that remain free within the database. These are reused by later writes, but
+    /// can only be returned to the filesystem by an offline vacuum.
+    pub free: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct IdxMeta {
     pub idxkeys: Map<IdxKey, IdxSlope>,
@@ -130,6 +151,13 @@ impl IdxMeta {
     }
 }
 
+/// The connection to a PostgreSQL database, that is used in place of SQLite.
+#[derive(Clone)]
+struct PostgresConfig {
+    url: String,
+    password: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct BackendConfig {
     path: String,
@@ -138,6 +166,9 @@ pub struct BackendConfig {
     fstype: FsType,
     // Cachesizes?
     arcsize: Option<usize>,
+    data_encryption_key: Option<KeyEncryptionKey>,
+    slow_search_threshold: Option<Duration>,
+    postgres: Option<PostgresConfig>,
 }
 
 impl BackendConfig {
@@ -148,9 +179,31 @@ impl BackendConfig {
             db_name: "main",
             fstype,
             arcsize,
+            data_encryption_key: None,
+            slow_search_threshold: None,
+            postgres: None,
         }
     }
 
+    /// Encrypt sensitive values at rest, with data encryption keys that are wrapped by this
+    /// key encryption key.
+    pub fn set_data_encryption_key(&mut self, key: KeyEncryptionKey) {
+        self.data_encryption_key = Some(key);
+    }
+
+    /// Searches that take at least this long are logged along with their query plan.
+    pub fn set_slow_search_threshold(&mut self, threshold: Duration) {
+        self.slow_search_threshold = Some(threshold);
+    }
+
+    /// Store the database in PostgreSQL at `url`, rather than in SQLite at `path`.
+    pub fn set_postgres(&mut self, url: &str, password: Option<String>) {
+        self.postgres = Some(PostgresConfig {
+            url: url.to_string(),
+            password,
+        });
+    }
+
     pub(crate) fn new_test(db_name: &'static str) -> Self {
         BackendConfig {
             pool_size: 1,
@@ -158,6 +211,9 @@ impl BackendConfig {
             db_name,
             fstype: FsType::Generic,
             arcsize: Some(2048),
+            data_encryption_key: None,
+            slow_search_threshold: None,
+            postgres: None,
         }
     }
 }
@@ -175,6 +231,11 @@ pub struct Backend {
     /// time series index of the full list of all changelog entries and what entries
     /// that are part of that change.
     ruv: Arc<ReplicationUpdateVector>,
+    /// Statistics of how searches were resolved, shared by all transactions.
+    stats: Arc<QueryStats>,
+    /// The metrics of this backend and the servers above it. Each backend has its own, so that
+    /// the domains served by this process report their metrics separately.
+    metrics: Arc<ServerMetrics>,
     cfg: BackendConfig,
 }
 
@@ -182,6 +243,8 @@ pub struct BackendReadTransaction<'a> {
     idlayer: IdlArcSqliteReadTransaction<'a>,
     idxmeta: CowCellReadTxn<IdxMeta>,
     ruv: ReplicationUpdateVectorReadTransaction<'a>,
+    stats: Arc<QueryStats>,
+    metrics: Arc<ServerMetrics>,
 }
 
 unsafe impl Sync for BackendReadTransaction<'_> {}
@@ -192,6 +255,8 @@ pub struct BackendWriteTransaction<'a> {
     idlayer: IdlArcSqliteWriteTransaction<'a>,
     idxmeta_wr: CowCellWriteTxn<'a, IdxMeta>,
     ruv: ReplicationUpdateVectorWriteTransaction<'a>,
+    stats: Arc<QueryStats>,
+    metrics: Arc<ServerMetrics>,
 }
 
 impl IdRawEntry {
@@ -204,13 +269,17 @@ impl IdRawEntry {
             .map(|dbe| (self.id, dbe))
     }
 
-    fn into_entry(self) -> Result<EntrySealedCommitted, OperationError> {
-        let db_e = serde_json::from_slice(self.data.as_slice()).map_err(|e| {
+    fn into_entry(
+        self,
+        data_keys: Option<&DataEncryptionKeys>,
+    ) -> Result<EntrySealedCommitted, OperationError> {
+        let mut db_e = serde_json::from_slice(self.data.as_slice()).map_err(|e| {
             admin_error!(?e, id = %self.id, "Serde JSON Error");
             let raw_str = String::from_utf8_lossy(self.data.as_slice());
             debug!(raw = %raw_str);
             OperationError::SerdeJsonError
         })?;
+        unseal_dbentry(data_keys, &mut db_e)?;
         // let id = u64::try_from(self.id).map_err(|_| OperationError::InvalidEntryId)?;
         Entry::from_dbentry(db_e, self.id).ok_or(OperationError::CorruptedEntry(self.id))
     }
@@ -225,6 +294,10 @@ pub trait BackendTransaction {
 
     fn get_idxmeta_ref(&self) -> &IdxMeta;
 
+    fn get_query_stats(&self) -> &QueryStats;
+
+    fn get_metrics(&self) -> &ServerMetrics;
+
     /// Recursively apply a filter, transforming into IdList's on the way. This builds a query
     /// execution log, so that it can be examined how an operation proceeded.
     #[allow(clippy::cognitive_complexity)]
@@ -652,6 +725,8 @@ pub trait BackendTransaction {
 
         trace!(filter_optimised = ?filt);
 
+        let start = Instant::now();
+
         let (idl, fplan) = trace_span!("be::search -> filter2idl")
             .in_scope(|| self.filter2idl(filt.to_inner(), FILTER_SEARCH_TEST_THRESHOLD))?;
 
@@ -694,6 +769,8 @@ pub trait BackendTransaction {
             e
         })?;
 
+        let candidates = entries.len();
+
         let mut entries_filtered = match idl {
             IdList::AllIds => trace_span!("be::search<entry::ftest::allids>").in_scope(|| {
                 entries
@@ -731,6 +808,24 @@ pub trait BackendTransaction {
         // Trim any excess capacity if needed
         entries_filtered.shrink_to_fit();
 
+        let elapsed = start.elapsed();
+        let slow = self.get_query_stats().record(&SearchStats {
+            plan: &fplan,
+            candidates,
+            results: entries_filtered.len(),
+            elapsed,
+        });
+
+        if slow {
+            warn!(
+                ?elapsed,
+                candidates,
+                results = entries_filtered.len(),
+                search_filter_executed_plan = %fplan,
+                "Slow search"
+            );
+        }
+
         Ok(entries_filtered)
     }
 
@@ -936,6 +1031,19 @@ pub trait BackendTransaction {
     }
 
     fn backup(&mut self, dst_path: &str) -> Result<(), OperationError> {
+        let serialized_entries_str = self.backup_data()?;
+
+        fs::write(dst_path, serialized_entries_str)
+            .map(|_| ())
+            .map_err(|e| {
+                admin_error!(?e, "fs::write error");
+                OperationError::FsError
+            })
+    }
+
+    /// Serialise the content of the database as a backup, so that it can be written to a
+    /// location other than a local file.
+    fn backup_data(&mut self) -> Result<String, OperationError> {
         let repl_meta = self.get_ruv().to_db_backup_ruv();
 
         // load all entries into RAM, may need to change this later
@@ -977,17 +1085,10 @@ pub trait BackendTransaction {
             entries,
         };
 
-        let serialized_entries_str = serde_json::to_string(&bak).map_err(|e| {
+        serde_json::to_string(&bak).map_err(|e| {
             admin_error!(?e, "serde error");
             OperationError::SerdeJsonError
-        })?;
-
-        fs::write(dst_path, serialized_entries_str)
-            .map(|_| ())
-            .map_err(|e| {
-                admin_error!(?e, "fs::write error");
-                OperationError::FsError
-            })
+        })
     }
 
     fn name2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError> {
@@ -1022,6 +1123,14 @@ impl<'a> BackendTransaction for BackendReadTransaction<'a> {
     fn get_idxmeta_ref(&self) -> &IdxMeta {
         &self.idxmeta
     }
+
+    fn get_query_stats(&self) -> &QueryStats {
+        &self.stats
+    }
+
+    fn get_metrics(&self) -> &ServerMetrics {
+        &self.metrics
+    }
 }
 
 impl BackendReadTransaction<'_> {
@@ -1064,6 +1173,14 @@ impl<'a> BackendTransaction for BackendWriteTransaction<'a> {
     fn get_idxmeta_ref(&self) -> &IdxMeta {
         &self.idxmeta_wr
     }
+
+    fn get_query_stats(&self) -> &QueryStats {
+        &self.stats
+    }
+
+    fn get_metrics(&self) -> &ServerMetrics {
+        &self.metrics
+    }
 }
 
 impl<'a> BackendWriteTransaction<'a> {
@@ -1422,6 +1539,12 @@ impl<'a> BackendWriteTransaction<'a> {
         Ok(sz)
     }
 
+    /// Return the space freed by purges to the filesystem where possible.
+    #[instrument(level = "debug", name = "be::compact", skip_all)]
+    pub fn compact(&mut self) -> Result<DbCompaction, OperationError> {
+        self.get_idlayer().compact()
+    }
+
     #[instrument(level = "debug", name = "be::update_idxmeta", skip_all)]
     pub fn update_idxmeta(&mut self, idxkeys: Vec<IdxKey>) -> Result<(), OperationError> {
         if self.is_idx_slopeyness_generated()? {
@@ -1768,6 +1891,86 @@ impl<'a> BackendWriteTransaction<'a> {
         Ok(())
     }
 
+    /// Rebuild the indexes of a single attribute, leaving all other indexes in place. This
+    /// allows an online reindex to proceed in small write transactions rather than holding
+    /// the write lock for the whole database at once.
+    #[instrument(level = "debug", skip(self))]
+    pub fn reindex_attribute(&mut self, attr: &Attribute) -> Result<(), OperationError> {
+        let idxkeys: Map<IdxKey, IdxSlope> = self
+            .idxmeta_wr
+            .idxkeys
+            .iter()
+            .filter(|(ikey, _)| ikey.attr == *attr)
+            .map(|(ikey, slope)| (ikey.clone(), *slope))
+            .collect();
+
+        if idxkeys.is_empty() {
+            debug!("Attribute has no indexes");
+            return Ok(());
+        }
+
+        idxkeys
+            .keys()
+            .try_for_each(|ikey| self.idlayer.create_idx(&ikey.attr, ikey.itype))?;
+
+        let entries = self
+            .idlayer
+            .get_identry(&IdList::AllIds)
+            .inspect_err(|err| {
+                error!(?err, "get_identry failure");
+            })?;
+
+        // Build the content of each index in memory, then replace what is stored.
+        let mut idls: BTreeMap<(IndexType, String), IDLBitRange> = BTreeMap::new();
+
+        for e in entries.iter() {
+            let e_id = e.get_id();
+            for (_, itype, idx_key) in Entry::idx_diff(&idxkeys, None, Some(e.as_ref()))
+                .into_iter()
+                .flatten()
+            {
+                idls.entry((itype, idx_key))
+                    .or_insert_with(IDLBitRange::new)
+                    .insert_id(e_id);
+            }
+        }
+
+        for ikey in idxkeys.keys() {
+            let index_name = format!("idx_{}_{}", ikey.itype.as_idx_str(), ikey.attr.as_str());
+            // Keys that no entry yields any more are written empty, which removes them.
+            let stale = self
+                .idlayer
+                .list_index_content(&index_name)?
+                .into_iter()
+                .filter(|(idx_key, _)| !idls.contains_key(&(ikey.itype, idx_key.clone())));
+
+            for (idx_key, _) in stale {
+                self.idlayer
+                    .write_idl(attr, ikey.itype, &idx_key, &IDLBitRange::new())?;
+            }
+        }
+
+        for ((itype, idx_key), idl) in idls.iter() {
+            self.idlayer.write_idl(attr, *itype, idx_key, idl)?;
+        }
+
+        self.idlayer.optimise_dirty_idls();
+
+        debug!(
+            indexes = idxkeys.len(),
+            keys = idls.len(),
+            "Reindexed attribute"
+        );
+        Ok(())
+    }
+
+    /// Recalculate the index slopes once an online reindex has rebuilt every attribute.
+    pub fn reindex_analyse(&mut self) -> Result<(), OperationError> {
+        self.idlayer.analyse_idx_slopes().inspect_err(|err| {
+            error!(?err, "index optimisation failed");
+        })
+    }
+
     /// ⚠️  - This function will destroy all indexes in the database.
     ///
     /// It should only be called internally by the backend in limited and
@@ -1817,6 +2020,31 @@ impl<'a> BackendWriteTransaction<'a> {
             OperationError::FsError
         })?;
 
+        self.restore_data(&serialized_string)
+    }
+
+    /// Replace the content of the database with a backup that was created by `backup_data`.
+    pub fn restore_data(&mut self, serialized_string: &str) -> Result<(), OperationError> {
+        let dbbak = parse_backup(serialized_string)?;
+        self.restore_backup(dbbak)
+    }
+
+    /// Replace the content of the database with the state it had at the time `until`. This
+    /// is reconstructed by replaying the changes recorded in a later backup over a base
+    /// backup that was taken before `until`.
+    pub fn restore_until(
+        &mut self,
+        base_serialized_string: &str,
+        changes_serialized_string: &str,
+        until: Duration,
+    ) -> Result<(), OperationError> {
+        let base = parse_backup(base_serialized_string)?;
+        let changes = parse_backup(changes_serialized_string)?;
+        let dbbak = restore::replay_until(base, changes, until)?;
+        self.restore_backup(dbbak)
+    }
+
+    fn restore_backup(&mut self, dbbak: DbBackup) -> Result<(), OperationError> {
         self.danger_delete_all_db_content().map_err(|e| {
             admin_error!("delete_all_db_content failed {:?}", e);
             e
@@ -1826,14 +2054,6 @@ impl<'a> BackendWriteTransaction<'a> {
         // load all entries into RAM, may need to change this later
         // if the size of the database compared to RAM is an issue
 
-        let dbbak_option: Result<DbBackup, serde_json::Error> =
-            serde_json::from_str(&serialized_string);
-
-        let dbbak = dbbak_option.map_err(|e| {
-            admin_error!("serde_json error {:?}", e);
-            OperationError::SerdeJsonError
-        })?;
-
         let (dbentries, repl_meta, maybe_version) = match dbbak {
             DbBackup::V1(dbentries) => (dbentries, None, None),
             DbBackup::V2 {
@@ -2014,6 +2234,7 @@ impl<'a> BackendWriteTransaction<'a> {
             mut idlayer,
             idxmeta_wr,
             ruv,
+            stats: _,
         } = self;
 
         // write the ruv content back to the db.
@@ -2146,12 +2367,16 @@ impl Backend {
         let ruv = Arc::new(ReplicationUpdateVector::default());
 
         // this has a ::memory() type, but will path == "" work?
-        let idlayer = Arc::new(IdlArcSqlite::new(&cfg, vacuum)?);
+        let metrics = Arc::new(ServerMetrics::new());
+        let idlayer = Arc::new(IdlArcSqlite::new(&cfg, vacuum, metrics.clone())?);
+        let stats = Arc::new(QueryStats::new(cfg.slow_search_threshold));
         let be = Backend {
             cfg,
             idlayer,
             ruv,
             idxmeta: Arc::new(CowCell::new(IdxMeta::new(idxkeys))),
+            stats,
+            metrics,
         };
 
         // Now complete our setup with a txn
@@ -2167,6 +2392,9 @@ impl Backend {
                 e
             })?;
 
+        // The data encryption keys must be installed before any entries are read.
+        be.setup_data_encryption()?;
+
         // Now rebuild the ruv.
         let mut be_write = be.write()?;
         be_write
@@ -2189,11 +2417,17 @@ impl Backend {
         self.idlayer.try_quiesce();
     }
 
+    pub fn metrics(&self) -> &ServerMetrics {
+        &self.metrics
+    }
+
     pub fn read(&self) -> Result<BackendReadTransaction, OperationError> {
         Ok(BackendReadTransaction {
             idlayer: self.idlayer.read()?,
             idxmeta: self.idxmeta.read(),
             ruv: self.ruv.read(),
+            stats: self.stats.clone(),
+            metrics: self.metrics.clone(),
         })
     }
 
@@ -2202,6 +2436,8 @@ impl Backend {
             idlayer: self.idlayer.write()?,
             idxmeta_wr: self.idxmeta.write(),
             ruv: self.ruv.write(),
+            stats: self.stats.clone(),
+            metrics: self.metrics.clone(),
         })
     }
 }
@@ -2937,6 +3173,68 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_be_reindex_attribute() {
+        run_test!(|be: &mut BackendWriteTransaction| {
+            let mut e1: Entry<EntryInit, EntryNew> = Entry::new();
+            e1.add_ava(Attribute::Name, Value::new_iname("william"));
+            e1.add_ava(
+                Attribute::Uuid,
+                Value::from("db237e8a-0079-4b8c-8a56-593b22aa44d1"),
+            );
+            let e1 = e1.into_sealed_new();
+
+            let mut e2: Entry<EntryInit, EntryNew> = Entry::new();
+            e2.add_ava(Attribute::Name, Value::new_iname("claire"));
+            e2.add_ava(
+                Attribute::Uuid,
+                Value::from("bd651620-00dd-426b-aaa0-4494f7b7906f"),
+            );
+            let e2 = e2.into_sealed_new();
+
+            be.create(&CID_ZERO, vec![e1, e2]).unwrap();
+
+            be.danger_purge_idxs().unwrap();
+
+            // Only the indexes of the requested attribute are rebuilt.
+            assert!(be.reindex_attribute(&Attribute::Name).is_ok());
+
+            idl_state!(
+                be,
+                Attribute::Name,
+                IndexType::Equality,
+                "william",
+                Some(vec![1])
+            );
+
+            idl_state!(
+                be,
+                Attribute::Name,
+                IndexType::Presence,
+                "_",
+                Some(vec![1, 2])
+            );
+
+            idl_state!(
+                be,
+                Attribute::Uuid,
+                IndexType::Equality,
+                "db237e8a-0079-4b8c-8a56-593b22aa44d1",
+                None::<Vec<u64>>
+            );
+
+            assert!(be.reindex_attribute(&Attribute::Uuid).is_ok());
+
+            idl_state!(
+                be,
+                Attribute::Uuid,
+                IndexType::Equality,
+                "db237e8a-0079-4b8c-8a56-593b22aa44d1",
+                Some(vec![1])
+            );
+        });
+    }
+
     #[test]
     fn test_be_index_create_delete_simple() {
         run_test!(|be: &mut BackendWriteTransaction| {
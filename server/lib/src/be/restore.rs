@@ -0,0 +1,381 @@
+//! Point in time reconstruction of the database content. Kanidm does not retain a changelog,
+//! so instead we take a base backup, and "replay" the attribute changes recorded in the change
+//! state of a later backup up to (and including) the requested time. Changes from the later
+//! backup that occurred after that time are discarded, leaving the values from the base.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use super::dbentry::{DbBackup, DbEntry, DbEntryVers};
+use super::dbrepl::{DbEntryChangeState, DbReplMeta};
+use super::dbvalue::{DbCidV1, DbValueSetV2};
+use super::keystorage::{KeyHandle, KeyHandleId};
+use crate::prelude::*;
+
+struct BackupV5 {
+    version: String,
+    db_s_uuid: Uuid,
+    db_d_uuid: Uuid,
+    db_ts_max: Duration,
+    keyhandles: BTreeMap<KeyHandleId, KeyHandle>,
+    ruv: BTreeSet<DbCidV1>,
+    entries: Vec<DbEntry>,
+}
+
+impl TryFrom<DbBackup> for BackupV5 {
+    type Error = OperationError;
+
+    fn try_from(bak: DbBackup) -> Result<Self, Self::Error> {
+        match bak {
+            DbBackup::V5 {
+                version,
+                db_s_uuid,
+                db_d_uuid,
+                db_ts_max,
+                keyhandles,
+                repl_meta: DbReplMeta::V1 { ruv },
+                entries,
+            } => Ok(BackupV5 {
+                version,
+                db_s_uuid,
+                db_d_uuid,
+                db_ts_max,
+                keyhandles,
+                ruv,
+                entries,
+            }),
+            _ => {
+                error!("Point in time restore requires backups created by this server version.");
+                Err(OperationError::DB0002MismatchedRestoreVersion)
+            }
+        }
+    }
+}
+
+pub(crate) fn parse_backup(serialized_string: &str) -> Result<DbBackup, OperationError> {
+    serde_json::from_str(serialized_string).map_err(|e| {
+        admin_error!("serde_json error {:?}", e);
+        OperationError::SerdeJsonError
+    })
+}
+
+/// Load the entries of a backup that was created by this server version, so that individual
+/// entries can be inspected or restored.
+pub(crate) fn backup_entries(serialized_string: &str) -> Result<Vec<DbEntry>, OperationError> {
+    let bak = BackupV5::try_from(parse_backup(serialized_string)?)?;
+
+    if bak.version != env!("KANIDM_PKG_SERIES") {
+        error!(
+            "The provided backup data is from server version {} and is unable to be restored on this instance ({})",
+            bak.version,
+            env!("KANIDM_PKG_SERIES")
+        );
+        return Err(OperationError::DB0001MismatchedRestoreVersion);
+    }
+
+    Ok(bak.entries)
+}
+
+pub(crate) fn db_entry_uuid(db_e: &DbEntry) -> Option<Uuid> {
+    let DbEntryVers::V3 { attrs, .. } = &db_e.ent;
+    match attrs.get(&Attribute::Uuid) {
+        Some(DbValueSetV2::Uuid(uuids)) => uuids.first().copied(),
+        _ => None,
+    }
+}
+
+/// Merge the changes of `changes` into `base`, as they existed at the time `until`. Both backups
+/// must be from the same domain and server version, and `base` must have been taken before
+/// `until`.
+pub(crate) fn replay_until(
+    base: DbBackup,
+    changes: DbBackup,
+    until: Duration,
+) -> Result<DbBackup, OperationError> {
+    let base = BackupV5::try_from(base)?;
+    let changes = BackupV5::try_from(changes)?;
+
+    if base.version != changes.version {
+        error!(
+            "The provided backups are from different server versions ({} and {})",
+            base.version, changes.version
+        );
+        return Err(OperationError::DB0001MismatchedRestoreVersion);
+    }
+
+    if base.db_d_uuid != changes.db_d_uuid {
+        error!("The provided backups are from different domains");
+        return Err(OperationError::DB0005MismatchedRestoreDomain);
+    }
+
+    if base.db_ts_max > until {
+        warn!("The base backup contains changes that are newer than the requested restore time. These changes will be retained.");
+    }
+
+    let mut base_entries: BTreeMap<Uuid, DbEntry> = BTreeMap::new();
+    for db_e in base.entries {
+        let u = db_entry_uuid(&db_e).ok_or_else(|| {
+            error!("Base backup contains an entry without a uuid");
+            OperationError::InvalidDbState
+        })?;
+        base_entries.insert(u, db_e);
+    }
+
+    let mut entries = Vec::with_capacity(changes.entries.len());
+    for later in changes.entries {
+        let u = db_entry_uuid(&later).ok_or_else(|| {
+            error!("Changes backup contains an entry without a uuid");
+            OperationError::InvalidDbState
+        })?;
+
+        let base_e = base_entries.remove(&u);
+        if let Some(db_e) = replay_entry(u, base_e, later, until) {
+            entries.push(db_e);
+        }
+    }
+
+    // Anything left only existed in the base, so it must be retained.
+    entries.extend(base_entries.into_values());
+
+    let mut ruv = base.ruv;
+    ruv.extend(changes.ruv.into_iter().filter(|cid| cid.timestamp <= until));
+
+    info!(
+        "Replayed changes until {:?}, {} entries will be restored",
+        until,
+        entries.len()
+    );
+
+    Ok(DbBackup::V5 {
+        version: base.version,
+        db_s_uuid: changes.db_s_uuid,
+        db_d_uuid: changes.db_d_uuid,
+        // Ensure that any new changes are always ahead of the ones we have restored.
+        db_ts_max: std::cmp::max(base.db_ts_max, changes.db_ts_max),
+        keyhandles: changes.keyhandles,
+        repl_meta: DbReplMeta::V1 { ruv },
+        entries,
+    })
+}
+
+fn replay_entry(
+    u: Uuid,
+    base: Option<DbEntry>,
+    later: DbEntry,
+    until: Duration,
+) -> Option<DbEntry> {
+    let DbEntryVers::V3 {
+        changestate,
+        attrs: mut later_attrs,
+    } = later.ent;
+
+    let later_changes = match changestate {
+        DbEntryChangeState::V1Tombstone { at } => {
+            if at.timestamp <= until {
+                // Was already a tombstone at the restore time.
+                return Some(DbEntry {
+                    ent: DbEntryVers::V3 {
+                        changestate: DbEntryChangeState::V1Tombstone { at },
+                        attrs: later_attrs,
+                    },
+                });
+            }
+
+            if base.is_none() {
+                warn!(
+                    ?u,
+                    "Entry was created and tombstoned after the base backup, it can not be restored"
+                );
+            }
+            return base;
+        }
+        DbEntryChangeState::V1Live { at, changes } => {
+            if at.timestamp > until {
+                // Created after the restore time.
+                return base;
+            }
+
+            if changes.values().all(|cid| cid.timestamp <= until) {
+                return Some(DbEntry {
+                    ent: DbEntryVers::V3 {
+                        changestate: DbEntryChangeState::V1Live { at, changes },
+                        attrs: later_attrs,
+                    },
+                });
+            }
+
+            changes
+        }
+    };
+
+    let Some(DbEntry {
+        ent:
+            DbEntryVers::V3 {
+                changestate:
+                    DbEntryChangeState::V1Live {
+                        at,
+                        changes: mut merged,
+                    },
+                mut attrs,
+            },
+    }) = base
+    else {
+        // We can't recover what this entry looked like at the restore time, so the closest
+        // we can get is the content in the later backup.
+        warn!(
+            ?u,
+            "Entry was modified after the restore time, but is not present in the base backup. The restored entry may contain changes made after the restore time."
+        );
+        // Only a live entry can reach here.
+        let at = later_changes.values().min().cloned().unwrap_or(DbCidV1 {
+            timestamp: until,
+            server_id: u,
+        });
+        return Some(DbEntry {
+            ent: DbEntryVers::V3 {
+                changestate: DbEntryChangeState::V1Live {
+                    at,
+                    changes: later_changes,
+                },
+                attrs: later_attrs,
+            },
+        });
+    };
+
+    for (attr, cid) in later_changes {
+        if cid.timestamp > until {
+            // Retain the base value.
+            continue;
+        }
+
+        match later_attrs.remove(&attr) {
+            Some(vs) => {
+                attrs.insert(attr.clone(), vs);
+            }
+            None => {
+                attrs.remove(&attr);
+            }
+        }
+        merged.insert(attr, cid);
+    }
+
+    Some(DbEntry {
+        ent: DbEntryVers::V3 {
+            changestate: DbEntryChangeState::V1Live {
+                at,
+                changes: merged,
+            },
+            attrs,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cid(secs: u64) -> DbCidV1 {
+        DbCidV1 {
+            timestamp: Duration::from_secs(secs),
+            server_id: Uuid::nil(),
+        }
+    }
+
+    fn live(u: Uuid, at: u64, name: (&str, u64), desc: Option<(&str, u64)>) -> DbEntry {
+        let mut attrs = BTreeMap::new();
+        let mut changes = BTreeMap::new();
+        attrs.insert(Attribute::Uuid, DbValueSetV2::Uuid(vec![u]));
+        changes.insert(Attribute::Uuid, cid(at));
+        attrs.insert(
+            Attribute::Name,
+            DbValueSetV2::Iname(vec![name.0.to_string()]),
+        );
+        changes.insert(Attribute::Name, cid(name.1));
+        if let Some((d, ts)) = desc {
+            attrs.insert(
+                Attribute::Description,
+                DbValueSetV2::Utf8(vec![d.to_string()]),
+            );
+            changes.insert(Attribute::Description, cid(ts));
+        }
+        DbEntry {
+            ent: DbEntryVers::V3 {
+                changestate: DbEntryChangeState::V1Live {
+                    at: cid(at),
+                    changes,
+                },
+                attrs,
+            },
+        }
+    }
+
+    fn backup(ts_max: u64, entries: Vec<DbEntry>) -> DbBackup {
+        DbBackup::V5 {
+            version: "test".to_string(),
+            db_s_uuid: Uuid::nil(),
+            db_d_uuid: Uuid::nil(),
+            db_ts_max: Duration::from_secs(ts_max),
+            keyhandles: BTreeMap::new(),
+            repl_meta: DbReplMeta::V1 {
+                ruv: (0..=ts_max).map(cid).collect(),
+            },
+            entries,
+        }
+    }
+
+    fn attrs_of(bak: &DbBackup, u: Uuid) -> Option<&BTreeMap<Attribute, DbValueSetV2>> {
+        let DbBackup::V5 { entries, .. } = bak else {
+            return None;
+        };
+        entries
+            .iter()
+            .find(|e| db_entry_uuid(e) == Some(u))
+            .map(|e| {
+                let DbEntryVers::V3 { attrs, .. } = &e.ent;
+                attrs
+            })
+    }
+
+    #[test]
+    fn test_be_restore_replay_until() {
+        let u_a = Uuid::new_v4();
+        let u_b = Uuid::new_v4();
+        let u_c = Uuid::new_v4();
+
+        let base = backup(10, vec![live(u_a, 1, ("a", 1), None)]);
+        let changes = backup(
+            30,
+            vec![
+                // Renamed at 15, described at 25.
+                live(u_a, 1, ("a_renamed", 15), Some(("later", 25))),
+                // Created at 12
+                live(u_b, 12, ("b", 12), None),
+                // Created at 22
+                live(u_c, 22, ("c", 22), None),
+            ],
+        );
+
+        let restored = replay_until(base, changes, Duration::from_secs(20)).expect("replay failed");
+
+        let a = attrs_of(&restored, u_a).expect("a missing");
+        assert_eq!(
+            a.get(&Attribute::Name),
+            Some(&DbValueSetV2::Iname(vec!["a_renamed".to_string()]))
+        );
+        assert!(a.get(&Attribute::Description).is_none());
+
+        assert!(attrs_of(&restored, u_b).is_some());
+        assert!(attrs_of(&restored, u_c).is_none());
+
+        let DbBackup::V5 {
+            repl_meta: DbReplMeta::V1 { ruv },
+            ..
+        } = restored
+        else {
+            panic!("invalid backup version");
+        };
+        assert!(ruv.iter().all(|c| c.timestamp <= Duration::from_secs(20)));
+    }
+}
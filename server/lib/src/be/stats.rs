@@ -0,0 +1,190 @@
+//! Statistics of how the query planner resolved searches. These are kept in memory for the
+//! life of the server, and are used to recommend the indexes that would benefit the searches
+//! that have been run, in the same way that a sql database would suggest missing indexes.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use kanidm_proto::internal::{IndexRecommendation, QueryAnalysisReport};
+
+use crate::be::{IdxKey, IdxMeta};
+use crate::filter::FilterPlan;
+use crate::prelude::*;
+use crate::value::IndexType;
+
+/// The statistics of a single search.
+pub struct SearchStats<'a> {
+    pub plan: &'a FilterPlan,
+    /// The number of entries loaded from the candidate set.
+    pub candidates: usize,
+    pub results: usize,
+    pub elapsed: Duration,
+}
+
+#[derive(Default)]
+struct MissingIndexStats {
+    searches: u64,
+    candidates: u64,
+    elapsed: Duration,
+}
+
+#[derive(Default)]
+struct QueryStatsInner {
+    searches: u64,
+    slow_searches: u64,
+    index_hits: u64,
+    index_misses: u64,
+    candidates: u64,
+    results: u64,
+    elapsed: Duration,
+    missing: BTreeMap<(Attribute, IndexType), MissingIndexStats>,
+}
+
+pub struct QueryStats {
+    slow_search_threshold: Option<Duration>,
+    inner: Mutex<QueryStatsInner>,
+}
+
+impl QueryStats {
+    pub fn new(slow_search_threshold: Option<Duration>) -> Self {
+        QueryStats {
+            slow_search_threshold,
+            inner: Mutex::new(QueryStatsInner::default()),
+        }
+    }
+
+    /// Record the statistics of a search. Returns true if the search was slower than the
+    /// configured threshold.
+    pub fn record(&self, search: &SearchStats) -> bool {
+        let mut hits = 0;
+        let mut misses = Vec::with_capacity(0);
+        search.plan.index_usage(&mut hits, &mut misses);
+
+        let slow = self
+            .slow_search_threshold
+            .map(|threshold| search.elapsed >= threshold)
+            .unwrap_or(false);
+
+        // Statistics are advisory, so a poisoned lock only means they stop being collected.
+        let Ok(mut inner) = self.inner.lock() else {
+            return slow;
+        };
+
+        inner.searches += 1;
+        if slow {
+            inner.slow_searches += 1;
+        }
+        inner.index_hits += hits as u64;
+        inner.index_misses += misses.len() as u64;
+        inner.candidates += search.candidates as u64;
+        inner.results += search.results as u64;
+        inner.elapsed += search.elapsed;
+
+        // A filter may name the same attribute more than once, but it's still one search.
+        misses.sort_unstable();
+        misses.dedup();
+
+        for key in misses {
+            let missing = inner.missing.entry(key).or_default();
+            missing.searches += 1;
+            missing.candidates += search.candidates as u64;
+            missing.elapsed += search.elapsed;
+        }
+
+        slow
+    }
+
+    /// Summarise the searches so far. Indexes that have been added since a search was run are
+    /// not recommended again.
+    pub fn report(&self, idxmeta: &IdxMeta) -> Result<QueryAnalysisReport, OperationError> {
+        let inner = self.inner.lock().map_err(|_| {
+            error!("query statistics lock is poisoned");
+            OperationError::InvalidState
+        })?;
+
+        let mut recommendations: Vec<_> = inner
+            .missing
+            .iter()
+            .filter(|((attr, itype), _)| {
+                !idxmeta
+                    .idxkeys
+                    .contains_key(&IdxKey::new(attr.clone(), *itype))
+            })
+            .map(|((attr, itype), missing)| IndexRecommendation {
+                attribute: attr.to_string(),
+                index_type: itype.to_string(),
+                searches: missing.searches,
+                candidates: missing.candidates,
+                time_ms: missing.elapsed.as_millis() as u64,
+            })
+            .collect();
+
+        recommendations.sort_by(|a, b| {
+            b.time_ms
+                .cmp(&a.time_ms)
+                .then_with(|| b.searches.cmp(&a.searches))
+        });
+
+        Ok(QueryAnalysisReport {
+            searches: inner.searches,
+            slow_searches: inner.slow_searches,
+            index_hits: inner.index_hits,
+            index_misses: inner.index_misses,
+            candidates: inner.candidates,
+            results: inner.results,
+            time_ms: inner.elapsed.as_millis() as u64,
+            recommendations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueryStats, SearchStats};
+    use crate::be::IdxMeta;
+    use crate::filter::FilterPlan;
+    use crate::prelude::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_be_query_stats_report() {
+        let stats = QueryStats::new(Some(Duration::from_millis(100)));
+
+        let plan = FilterPlan::AndPartial(vec![
+            FilterPlan::EqIndexed(Attribute::Class, "person".to_string()),
+            FilterPlan::EqUnindexed(Attribute::DisplayName),
+        ]);
+
+        assert!(!stats.record(&SearchStats {
+            plan: &plan,
+            candidates: 10,
+            results: 1,
+            elapsed: Duration::from_millis(5),
+        }));
+
+        assert!(stats.record(&SearchStats {
+            plan: &plan,
+            candidates: 20,
+            results: 2,
+            elapsed: Duration::from_millis(150),
+        }));
+
+        let report = stats
+            .report(&IdxMeta::new(Default::default()))
+            .expect("Failed to generate report");
+
+        assert_eq!(report.searches, 2);
+        assert_eq!(report.slow_searches, 1);
+        assert_eq!(report.index_hits, 2);
+        assert_eq!(report.index_misses, 2);
+        assert_eq!(report.candidates, 30);
+        assert_eq!(report.recommendations.len(), 1);
+
+        let rec = &report.recommendations[0];
+        assert_eq!(rec.attribute, Attribute::DisplayName.to_string());
+        assert_eq!(rec.index_type, "EQUALITY");
+        assert_eq!(rec.searches, 2);
+        assert_eq!(rec.time_ms, 155);
+    }
+}
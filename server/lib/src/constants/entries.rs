@@ -22,9 +22,11 @@ pub enum EntryClass {
     AccessControlReceiverEntryManager,
     AccessControlReceiverGroup,
     AccessControlSearch,
+    AccessControlTargetGroup,
     AccessControlTargetScope,
     Account,
     AccountPolicy,
+    Announcement,
     Application,
     AttributeType,
     Builtin,
@@ -32,27 +34,41 @@ pub enum EntryClass {
     ClassType,
     ClientCertificate,
     Conflict,
+    Contractor,
     DomainInfo,
     DynGroup,
     ExtensibleObject,
     Group,
+    GroupMembershipRequest,
+    HbacRule,
+    Host,
+    HostGroup,
     KeyProvider,
     KeyProviderInternal,
     KeyObject,
     KeyObjectJwtEs256,
+    KeyObjectJwtRs256,
     KeyObjectJweA128GCM,
+    KeyObjectReplTrustAnchor,
+    KeyObjectSshCa,
+    KeyObjectSaml2Signing,
+    KeyObjectKerberos,
     KeyObjectInternal,
     MemberOf,
+    MigrationRecord,
     OAuth2ResourceServer,
     OAuth2ResourceServerBasic,
     OAuth2ResourceServerPublic,
+    OAuth2ResourceServerNative,
     OAuth2DeviceCodeSession,
     Object,
+    OidcUpstream,
     OrgPerson,
     Person,
     PosixAccount,
     PosixGroup,
     Recycled,
+    Saml2ServiceProvider,
     Service,
     ServiceAccount,
     SyncAccount,
@@ -62,6 +78,7 @@ pub enum EntryClass {
     System,
     SystemInfo,
     SystemConfig,
+    Webhook,
     #[cfg(any(test, debug_assertions))]
     TestClass,
 }
@@ -76,9 +93,11 @@ impl From<EntryClass> for &'static str {
             EntryClass::AccessControlReceiverEntryManager => ACCESS_CONTROL_RECEIVER_ENTRY_MANAGER,
             EntryClass::AccessControlReceiverGroup => ACCESS_CONTROL_RECEIVER_GROUP,
             EntryClass::AccessControlSearch => ACCESS_CONTROL_SEARCH,
+            EntryClass::AccessControlTargetGroup => ACCESS_CONTROL_TARGET_GROUP,
             EntryClass::AccessControlTargetScope => ACCESS_CONTROL_TARGET_SCOPE,
             EntryClass::Account => ENTRYCLASS_ACCOUNT,
             EntryClass::AccountPolicy => ENTRYCLASS_ACCOUNT_POLICY,
+            EntryClass::Announcement => ENTRYCLASS_ANNOUNCEMENT,
             EntryClass::Application => ENTRYCLASS_APPLICATION,
             EntryClass::AttributeType => ENTRYCLASS_ATTRIBUTE_TYPE,
             EntryClass::Builtin => ENTRYCLASS_BUILTIN,
@@ -86,27 +105,41 @@ impl From<EntryClass> for &'static str {
             EntryClass::ClassType => ENTRYCLASS_CLASS_TYPE,
             EntryClass::ClientCertificate => ENTRYCLASS_CLIENT_CERTIFICATE,
             EntryClass::Conflict => ENTRYCLASS_CONFLICT,
+            EntryClass::Contractor => ENTRYCLASS_CONTRACTOR,
             EntryClass::DomainInfo => ENTRYCLASS_DOMAIN_INFO,
             EntryClass::DynGroup => ENTRYCLASS_DYN_GROUP,
             EntryClass::ExtensibleObject => ENTRYCLASS_EXTENSIBLE_OBJECT,
             EntryClass::Group => ENTRYCLASS_GROUP,
+            EntryClass::GroupMembershipRequest => ENTRYCLASS_GROUP_MEMBERSHIP_REQUEST,
+            EntryClass::HbacRule => ENTRYCLASS_HBAC_RULE,
+            EntryClass::Host => ENTRYCLASS_HOST,
+            EntryClass::HostGroup => ENTRYCLASS_HOST_GROUP,
             EntryClass::KeyProvider => ENTRYCLASS_KEY_PROVIDER,
             EntryClass::KeyProviderInternal => ENTRYCLASS_KEY_PROVIDER_INTERNAL,
             EntryClass::KeyObject => ENTRYCLASS_KEY_OBJECT,
             EntryClass::KeyObjectJwtEs256 => ENTRYCLASS_KEY_OBJECT_JWT_ES256,
+            EntryClass::KeyObjectJwtRs256 => ENTRYCLASS_KEY_OBJECT_JWT_RS256,
             EntryClass::KeyObjectJweA128GCM => ENTRYCLASS_KEY_OBJECT_JWE_A128GCM,
+            EntryClass::KeyObjectReplTrustAnchor => ENTRYCLASS_KEY_OBJECT_REPL_TRUST_ANCHOR,
+            EntryClass::KeyObjectSshCa => ENTRYCLASS_KEY_OBJECT_SSH_CA,
+            EntryClass::KeyObjectSaml2Signing => ENTRYCLASS_KEY_OBJECT_SAML2_SIGNING,
+            EntryClass::KeyObjectKerberos => ENTRYCLASS_KEY_OBJECT_KERBEROS,
             EntryClass::KeyObjectInternal => ENTRYCLASS_KEY_OBJECT_INTERNAL,
             EntryClass::MemberOf => ENTRYCLASS_MEMBER_OF,
+            EntryClass::MigrationRecord => ENTRYCLASS_MIGRATION_RECORD,
             EntryClass::OAuth2DeviceCodeSession => OAUTH2_DEVICE_CODE_SESSION,
             EntryClass::OAuth2ResourceServer => OAUTH2_RESOURCE_SERVER,
             EntryClass::OAuth2ResourceServerBasic => OAUTH2_RESOURCE_SERVER_BASIC,
             EntryClass::OAuth2ResourceServerPublic => OAUTH2_RESOURCE_SERVER_PUBLIC,
+            EntryClass::OAuth2ResourceServerNative => OAUTH2_RESOURCE_SERVER_NATIVE,
             EntryClass::Object => ENTRYCLASS_OBJECT,
+            EntryClass::OidcUpstream => ENTRYCLASS_OIDC_UPSTREAM,
             EntryClass::OrgPerson => ENTRYCLASS_ORG_PERSON,
             EntryClass::Person => ENTRYCLASS_PERSON,
             EntryClass::PosixAccount => ENTRYCLASS_POSIX_ACCOUNT,
             EntryClass::PosixGroup => ENTRYCLASS_POSIX_GROUP,
             EntryClass::Recycled => ENTRYCLASS_RECYCLED,
+            EntryClass::Saml2ServiceProvider => ENTRYCLASS_SAML2_SERVICE_PROVIDER,
             EntryClass::Service => ENTRYCLASS_SERVICE,
             EntryClass::ServiceAccount => ENTRYCLASS_SERVICE_ACCOUNT,
             EntryClass::SyncAccount => ENTRYCLASS_SYNC_ACCOUNT,
@@ -118,6 +151,7 @@ impl From<EntryClass> for &'static str {
             #[cfg(any(test, debug_assertions))]
             EntryClass::TestClass => TEST_ENTRYCLASS_TEST_CLASS,
             EntryClass::User => ENTRYCLASS_USER,
+            EntryClass::Webhook => ENTRYCLASS_WEBHOOK,
         }
     }
 }
@@ -91,6 +91,9 @@ pub const PURGE_FREQUENCY: u64 = 60;
 #[cfg(not(test))]
 pub const PURGE_FREQUENCY: u64 = 600;
 
+/// How often audit events that have aged out of the hot tier are exported.
+pub const AUDIT_EXPORT_FREQUENCY: u64 = 3600;
+
 /// The number of delayed actions to consider per write transaction. Higher
 /// values allow more coalescing to occur, but may consume more ram and cause
 /// some latency while dequeuing and writing those operations.
@@ -125,10 +128,17 @@ pub const PW_MIN_LENGTH: u32 = 10;
 pub const MAXIMUM_AUTH_SESSION_EXPIRY: u32 = u32::MAX;
 // Default - sessions last for 1 day
 pub const DEFAULT_AUTH_SESSION_EXPIRY: u32 = 86400;
+// Minimum - sessions may be idle for at least 5 minutes, so that reading a page doesn't
+// expire the session.
+pub const MINIMUM_AUTH_SESSION_IDLE_TIMEOUT: u32 = 300;
 // Maximum - privileges last for 1 hour.
 pub const MAXIMUM_AUTH_PRIVILEGE_EXPIRY: u32 = 3600;
 // Default - privileges last for 10 minutes.
 pub const DEFAULT_AUTH_PRIVILEGE_EXPIRY: u32 = 600;
+// Default - failed authentications are counted towards a lockout for 1 hour.
+pub const DEFAULT_AUTH_LOCKOUT_WINDOW: u32 = 3600;
+// Default - a credential that reaches the lockout threshold is locked for 15 minutes.
+pub const DEFAULT_AUTH_LOCKOUT_DURATION: u32 = 900;
 // Default - directly privileged sessions only last 1 hour.
 pub const DEFAULT_AUTH_SESSION_LIMITED_EXPIRY: u32 = 3600;
 // Default - oauth refresh tokens last for 16 hours.
@@ -138,13 +148,88 @@ pub const OAUTH_REFRESH_TOKEN_EXPIRY: u64 = 3600 * 16;
 /// of the refresh token, which is bound to the issuing session.
 pub const OAUTH2_ACCESS_TOKEN_EXPIRY: u32 = 15 * 60;
 
+/// How long access tokens issued to native application clients should last. These clients
+/// can't hold a secret, so their tokens are kept short lived.
+pub const OAUTH2_NATIVE_ACCESS_TOKEN_EXPIRY: u32 = 5 * 60;
+
+// Native application refresh tokens last for 8 hours.
+pub const OAUTH2_NATIVE_REFRESH_TOKEN_EXPIRY: u64 = 3600 * 8;
+
+/// How long the previous secret of a confidential OAuth2 client remains valid after the
+/// secret is rotated, if no other validity is requested.
+pub const OAUTH2_PREVIOUS_SECRET_VALIDITY: u64 = 7 * 86400;
+
+/// How often the last use of an OAuth2 client secret is recorded. Recording every use would
+/// cause a write (and replication) for every token request.
+pub const OAUTH2_SECRET_LAST_USED_GRANULARITY: u64 = 3600;
+
+/// How often the last use of an api token is recorded, for the same reason.
+pub const API_TOKEN_LAST_USED_GRANULARITY: u64 = 3600;
+
+/// How often the last use of an auth session is recorded. This is shorter than for api tokens
+/// so that people can recognise the sessions they are actively using.
+pub const SESSION_LAST_USED_GRANULARITY: u64 = 900;
+
+/// How old a DPoP proof may be before it is rejected. Proofs are bound to a single request,
+/// so this only needs to allow for network latency and clock skew.
+pub const DPOP_PROOF_MAX_AGE: u64 = 60;
+
+/// How far in the future the expiry of a service account jwt assertion may be. Assertions are
+/// bound to the nonce of a single authentication, so they only need to live for a short time.
+pub const JWT_ASSERTION_MAX_LIFETIME: u64 = 300;
+
+/// The number of seconds that a session issued from a jwt assertion is valid for. The service
+/// account can sign a new assertion at any time, so these sessions are kept short.
+pub const JWT_ASSERTION_SESSION_EXPIRY: u64 = 900;
+
+/// How old a session proof may be before it is rejected. Browsers refresh their proof each
+/// minute, so this allows for a tab that was in the background.
+pub const SESSION_PROOF_MAX_AGE: u64 = 300;
+
+/// How far in the future a session proof may be made, to allow for the clock of the browser
+/// being slightly ahead.
+pub const SESSION_PROOF_MAX_SKEW: u64 = 5;
+
 /// The amount of time a suppliers clock can be "ahead" before
 /// we warn about possible clock synchronisation issues.
 pub const REPL_SUPPLIER_ADVANCE_WINDOW: Duration = Duration::from_secs(600);
 
-/// The number of days that the default replication MTLS cert lasts for when
-/// configured manually. Defaults to 4 years (including 1 day for the leap year).
-pub const REPL_MTLS_CERTIFICATE_DAYS: u32 = 1461;
+/// The number of days that a replication identity issued by a trust anchor is valid for.
+pub const REPL_MTLS_IDENTITY_DAYS: u32 = 90;
+
+/// A replication identity issued by a trust anchor is renewed when it has less than this
+/// many days remaining.
+pub const REPL_MTLS_IDENTITY_RENEW_DAYS: u32 = 30;
+
+/// The number of days that the RADIUS client certificate authority is valid for. Defaults
+/// to 10 years.
+pub const RADIUS_CA_CERTIFICATE_DAYS: u32 = 3652;
+
+/// The number of days that an issued RADIUS client certificate is valid for.
+pub const RADIUS_CLIENT_CERTIFICATE_DAYS: u32 = 365;
+
+/// The number of seconds that an issued ssh user certificate is valid for.
+pub const SSH_USER_CERTIFICATE_VALIDITY: u64 = 8 * 3600;
+
+/// Issued ssh user certificates are valid from this many seconds in the past, to tolerate
+/// clock skew between kanidm and ssh servers.
+pub const SSH_USER_CERTIFICATE_BACKDATE: u64 = 300;
+
+/// The number of seconds that an issued kerberos ticket is valid for.
+pub const KERBEROS_TICKET_VALIDITY: u64 = 10 * 3600;
+
+/// The default number of seconds that a host join token is valid for.
+pub const HOST_JOIN_TOKEN_DEFAULT_TTL: u64 = 3600;
+
+/// The maximum number of seconds that a host join token may be valid for.
+pub const HOST_JOIN_TOKEN_MAX_TTL: u64 = 7 * 86400;
+
+/// The number of seconds that the credential of an enrolled host is valid for. Hosts must
+/// rotate their credential before it expires, or they will need to be enrolled again.
+pub const HOST_CREDENTIAL_EXPIRY: u64 = 30 * 86400;
+
+/// The label of the api tokens that are issued to enrolled hosts.
+pub const HOST_CREDENTIAL_LABEL: &str = "host credential";
 
 /// The default number of entries that a user may retrieve in a search
 pub const DEFAULT_LIMIT_SEARCH_MAX_RESULTS: u64 = 1024;
@@ -76,6 +76,11 @@ pub const UUID_IDM_CLIENT_CERTIFICATE_ADMINS: Uuid = uuid!("00000000-0000-0000-0
 pub const UUID_IDM_APPLICATION_ADMINS: Uuid = uuid!("00000000-0000-0000-0000-000000000050");
 pub const UUID_IDM_MAIL_ADMINS: Uuid = uuid!("00000000-0000-0000-0000-000000000051");
 pub const UUID_IDM_MAIL_SERVERS: Uuid = uuid!("00000000-0000-0000-0000-000000000052");
+pub const UUID_IDM_OAUTH2_CLIENT_REGISTRARS: Uuid =
+    uuid!("00000000-0000-0000-0000-000000000053");
+pub const UUID_IDM_WHITE_PAGES_READERS: Uuid = uuid!("00000000-0000-0000-0000-000000000054");
+pub const UUID_IDM_SAML2_ADMINS: Uuid = uuid!("00000000-0000-0000-0000-000000000055");
+pub const UUID_IDM_IMPERSONATION_ADMINS: Uuid = uuid!("00000000-0000-0000-0000-000000000056");
 
 //
 pub const UUID_IDM_HIGH_PRIVILEGE: Uuid = uuid!("00000000-0000-0000-0000-000000001000");
@@ -329,6 +334,191 @@ pub const UUID_SCHEMA_ATTR_ALLOW_PRIMARY_CRED_FALLBACK: Uuid =
     uuid!("00000000-0000-0000-0000-ffff00000185");
 pub const UUID_SCHEMA_ATTR_DOMAIN_ALLOW_EASTER_EGGS: Uuid =
     uuid!("00000000-0000-0000-0000-ffff00000186");
+pub const UUID_SCHEMA_ATTR_AUTH_TRUSTED_NETWORK: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000188");
+pub const UUID_SCHEMA_ATTR_CREDENTIAL_TYPE_MINIMUM_UNTRUSTED_NETWORK: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000189");
+pub const UUID_SCHEMA_ATTR_SPONSOR: Uuid = uuid!("00000000-0000-0000-0000-ffff00000190");
+pub const UUID_SCHEMA_CLASS_CONTRACTOR: Uuid = uuid!("00000000-0000-0000-0000-ffff00000191");
+pub const UUID_SCHEMA_ATTR_CREDENTIAL_USAGE: Uuid = uuid!("00000000-0000-0000-0000-ffff00000192");
+pub const UUID_SCHEMA_ATTR_AUTH_LOCKOUT_THRESHOLD: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000193");
+pub const UUID_SCHEMA_ATTR_AUTH_LOCKOUT_WINDOW: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000194");
+pub const UUID_SCHEMA_ATTR_AUTH_LOCKOUT_DURATION: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000195");
+pub const UUID_SCHEMA_ATTR_AUTH_PASSWORD_HISTORY_COUNT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000196");
+pub const UUID_SCHEMA_ATTR_PASSWORD_HISTORY: Uuid = uuid!("00000000-0000-0000-0000-ffff00000197");
+pub const UUID_SCHEMA_ATTR_DENIED_PASSWORD_TERM: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000198");
+pub const UUID_SCHEMA_ATTR_WEBHOOK_URL: Uuid = uuid!("00000000-0000-0000-0000-ffff00000199");
+pub const UUID_SCHEMA_ATTR_WEBHOOK_SECRET: Uuid = uuid!("00000000-0000-0000-0000-ffff00000200");
+pub const UUID_SCHEMA_ATTR_WEBHOOK_FILTER: Uuid = uuid!("00000000-0000-0000-0000-ffff00000201");
+pub const UUID_SCHEMA_CLASS_WEBHOOK: Uuid = uuid!("00000000-0000-0000-0000-ffff00000202");
+pub const UUID_SCHEMA_ATTR_ALLOW_UNIX_PASSWORD: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000203");
+pub const UUID_SCHEMA_ATTR_SSH_PUBLICKEY_EXPIRY: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000204");
+pub const UUID_SCHEMA_ATTR_SSH_KEY_ALLOWED_TYPE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000205");
+pub const UUID_SCHEMA_ATTR_SSH_KEY_RSA_MINIMUM_BITS: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000206");
+pub const UUID_SCHEMA_ATTR_LDAP_GROUP_COMPAT: Uuid = uuid!("00000000-0000-0000-0000-ffff00000207");
+pub const UUID_SCHEMA_ATTR_POSIX_DEFAULT_SHELL: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000208");
+pub const UUID_SCHEMA_ATTR_POSIX_HOME_TEMPLATE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000209");
+pub const UUID_SCHEMA_ATTR_POSIX_GECOS_FORMAT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000210");
+pub const UUID_SCHEMA_ATTR_POSIX_HOME_SKELETON: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000211");
+pub const UUID_SCHEMA_ATTR_POSIX_HOME_QUOTA: Uuid = uuid!("00000000-0000-0000-0000-ffff00000212");
+pub const UUID_SCHEMA_ATTR_HBAC_MEMBER: Uuid = uuid!("00000000-0000-0000-0000-ffff00000213");
+pub const UUID_SCHEMA_ATTR_HBAC_HOST: Uuid = uuid!("00000000-0000-0000-0000-ffff00000214");
+pub const UUID_SCHEMA_ATTR_HBAC_SERVICE: Uuid = uuid!("00000000-0000-0000-0000-ffff00000215");
+pub const UUID_SCHEMA_ATTR_HBAC_DENY: Uuid = uuid!("00000000-0000-0000-0000-ffff00000216");
+pub const UUID_SCHEMA_CLASS_HBAC_RULE: Uuid = uuid!("00000000-0000-0000-0000-ffff00000217");
+pub const UUID_SCHEMA_ATTR_HOST_TAG: Uuid = uuid!("00000000-0000-0000-0000-ffff00000218");
+pub const UUID_SCHEMA_ATTR_HOST_GROUP_MEMBER: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000219");
+pub const UUID_SCHEMA_ATTR_HBAC_HOST_GROUP: Uuid = uuid!("00000000-0000-0000-0000-ffff00000220");
+pub const UUID_SCHEMA_CLASS_HOST_GROUP: Uuid = uuid!("00000000-0000-0000-0000-ffff00000221");
+pub const UUID_SCHEMA_ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000222");
+pub const UUID_SCHEMA_ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000223");
+pub const UUID_SCHEMA_ATTR_NOTIFICATION_OPT_OUT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000224");
+pub const UUID_SCHEMA_ATTR_ACCOUNT_LIFETIME: Uuid = uuid!("00000000-0000-0000-0000-ffff00000225");
+pub const UUID_SCHEMA_ATTR_ACCOUNT_ARCHIVE_DELAY: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000226");
+pub const UUID_SCHEMA_ATTR_DOMAIN_THEME: Uuid = uuid!("00000000-0000-0000-0000-ffff00000227");
+pub const UUID_SCHEMA_ATTR_UI_THEME: Uuid = uuid!("00000000-0000-0000-0000-ffff00000228");
+pub const UUID_SCHEMA_ATTR_ACP_TARGET_GROUP: Uuid = uuid!("00000000-0000-0000-0000-ffff00000229");
+pub const UUID_SCHEMA_CLASS_ACCESS_CONTROL_TARGET_GROUP: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000230");
+pub const UUID_SCHEMA_CLASS_KEY_OBJECT_REPL_TRUST_ANCHOR: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000231");
+pub const UUID_SCHEMA_ATTR_OAUTH2_REDIRECT_URI_MODE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000232");
+pub const UUID_SCHEMA_CLASS_OAUTH2_RS_NATIVE: Uuid = uuid!("00000000-0000-0000-0000-ffff00000233");
+pub const UUID_SCHEMA_ATTR_OAUTH2_ALLOWED_GRANT_TYPES: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000234");
+pub const UUID_SCHEMA_ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000235");
+pub const UUID_SCHEMA_ATTR_OAUTH2_RS_SCOPE_DESCRIPTION: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000236");
+pub const UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000237");
+pub const UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_LAST_USED: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000238");
+pub const UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000239");
+pub const UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000240");
+pub const UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000241");
+pub const UUID_SCHEMA_ATTR_OAUTH2_RS_ASSIGNED_MEMBER: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000242");
+pub const UUID_SCHEMA_CLASS_KEY_OBJECT_SSH_CA: Uuid = uuid!("00000000-0000-0000-0000-ffff00000243");
+pub const UUID_SCHEMA_ATTR_HOST_JOIN_TOKEN: Uuid = uuid!("00000000-0000-0000-0000-ffff00000244");
+pub const UUID_SCHEMA_ATTR_HOST_JOIN_TOKEN_EXPIRY: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000245");
+pub const UUID_SCHEMA_CLASS_HOST: Uuid = uuid!("00000000-0000-0000-0000-ffff00000246");
+pub const UUID_SCHEMA_ATTR_ALLOW_API_TOKENS: Uuid = uuid!("00000000-0000-0000-0000-ffff00000247");
+pub const UUID_SCHEMA_ATTR_LOGIN_HOST_TAG: Uuid = uuid!("00000000-0000-0000-0000-ffff00000248");
+pub const UUID_SCHEMA_ATTR_DOMAIN_LOGIN_BANNER: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000249");
+pub const UUID_SCHEMA_ATTR_DOMAIN_PRIMARY_COLOUR: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000250");
+pub const UUID_SCHEMA_ATTR_HOST_KIOSK_MODE: Uuid = uuid!("00000000-0000-0000-0000-ffff00000251");
+pub const UUID_SCHEMA_ATTR_HOST_KIOSK_CACHE_TIMEOUT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000252");
+pub const UUID_SCHEMA_ATTR_HOST_KIOSK_GUEST_GROUP: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000253");
+pub const UUID_SCHEMA_ATTR_MIGRATION_NAME: Uuid = uuid!("00000000-0000-0000-0000-ffff00000254");
+pub const UUID_SCHEMA_ATTR_MIGRATION_FROM_LEVEL: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000255");
+pub const UUID_SCHEMA_ATTR_MIGRATION_TO_LEVEL: Uuid = uuid!("00000000-0000-0000-0000-ffff00000256");
+pub const UUID_SCHEMA_ATTR_MIGRATION_APPLIED_AT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000257");
+pub const UUID_SCHEMA_ATTR_MIGRATION_DURATION: Uuid = uuid!("00000000-0000-0000-0000-ffff00000258");
+pub const UUID_SCHEMA_ATTR_MIGRATION_ENTRIES_CHANGED: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000259");
+pub const UUID_SCHEMA_CLASS_MIGRATION_RECORD: Uuid = uuid!("00000000-0000-0000-0000-ffff00000260");
+pub const UUID_SCHEMA_ATTR_ACP_STAGED: Uuid = uuid!("00000000-0000-0000-0000-ffff00000261");
+pub const UUID_SCHEMA_ATTR_ALLOW_EMAIL_LINK: Uuid = uuid!("00000000-0000-0000-0000-ffff00000262");
+pub const UUID_SCHEMA_ATTR_ANNOUNCEMENT_MESSAGE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000263");
+pub const UUID_SCHEMA_ATTR_ANNOUNCEMENT_SEVERITY: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000264");
+pub const UUID_SCHEMA_ATTR_ANNOUNCEMENT_START: Uuid = uuid!("00000000-0000-0000-0000-ffff00000265");
+pub const UUID_SCHEMA_ATTR_ANNOUNCEMENT_END: Uuid = uuid!("00000000-0000-0000-0000-ffff00000266");
+pub const UUID_SCHEMA_ATTR_ANNOUNCEMENT_CLI: Uuid = uuid!("00000000-0000-0000-0000-ffff00000267");
+pub const UUID_SCHEMA_CLASS_ANNOUNCEMENT: Uuid = uuid!("00000000-0000-0000-0000-ffff00000268");
+pub const UUID_SCHEMA_ATTR_OIDC_UPSTREAM_ISSUER: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000269");
+pub const UUID_SCHEMA_ATTR_OIDC_UPSTREAM_CLIENT_ID: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000270");
+pub const UUID_SCHEMA_ATTR_OIDC_UPSTREAM_CLIENT_SECRET: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000271");
+pub const UUID_SCHEMA_ATTR_OIDC_UPSTREAM_AUTHORISATION_ENDPOINT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000272");
+pub const UUID_SCHEMA_ATTR_OIDC_UPSTREAM_TOKEN_ENDPOINT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000273");
+pub const UUID_SCHEMA_ATTR_OIDC_UPSTREAM_JWKS_URI: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000274");
+pub const UUID_SCHEMA_CLASS_OIDC_UPSTREAM: Uuid = uuid!("00000000-0000-0000-0000-ffff00000275");
+pub const UUID_SCHEMA_ATTR_PHONE: Uuid = uuid!("00000000-0000-0000-0000-ffff00000276");
+pub const UUID_SCHEMA_ATTR_WHITE_PAGES_TRUSTED_NETWORK: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000277");
+pub const UUID_SCHEMA_ATTR_WHITE_PAGES_RATE_LIMIT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000278");
+pub const UUID_SCHEMA_ATTR_TELEPHONE_NUMBER: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000279");
+pub const UUID_SCHEMA_ATTR_SAML2_ENTITY_ID: Uuid = uuid!("00000000-0000-0000-0000-ffff00000280");
+pub const UUID_SCHEMA_ATTR_SAML2_ACS_URL: Uuid = uuid!("00000000-0000-0000-0000-ffff00000281");
+pub const UUID_SCHEMA_ATTR_SAML2_GROUP_MAP: Uuid = uuid!("00000000-0000-0000-0000-ffff00000282");
+pub const UUID_SCHEMA_CLASS_SAML2_SERVICE_PROVIDER: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000283");
+pub const UUID_SCHEMA_CLASS_KEY_OBJECT_SAML2_SIGNING: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000284");
+pub const UUID_SCHEMA_ATTR_KERBEROS_SERVICE_PRINCIPAL: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000285");
+pub const UUID_SCHEMA_CLASS_KEY_OBJECT_KERBEROS: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000286");
+pub const UUID_SCHEMA_ATTR_SYNC_CONFLICT: Uuid = uuid!("00000000-0000-0000-0000-ffff00000287");
+pub const UUID_SCHEMA_ATTR_OAUTH2_JWT_SIGN_ALG: Uuid = uuid!("00000000-0000-0000-0000-ffff00000288");
+pub const UUID_SCHEMA_CLASS_KEY_OBJECT_JWT_RS256: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000289");
+pub const UUID_SCHEMA_ATTR_OAUTH2_RS_COMPUTED_CLAIM: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000290");
+pub const UUID_SCHEMA_ATTR_MEMBER_VALID_UNTIL: Uuid = uuid!("00000000-0000-0000-0000-ffff00000291");
+pub const UUID_SCHEMA_ATTR_MEMBERSHIP_REQUESTABLE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000292");
+pub const UUID_SCHEMA_ATTR_MEMBERSHIP_REQUEST_GROUP: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000293");
+pub const UUID_SCHEMA_ATTR_MEMBERSHIP_REQUESTER: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000294");
+pub const UUID_SCHEMA_ATTR_MEMBERSHIP_REQUEST_STATUS: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000295");
+pub const UUID_SCHEMA_ATTR_MEMBERSHIP_REQUEST_DECIDED_BY: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000296");
+pub const UUID_SCHEMA_ATTR_MEMBERSHIP_REQUEST_VALID_UNTIL: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000297");
+pub const UUID_SCHEMA_CLASS_GROUP_MEMBERSHIP_REQUEST: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000298");
+pub const UUID_SCHEMA_ATTR_PARENT_GROUP: Uuid = uuid!("00000000-0000-0000-0000-ffff00000299");
+pub const UUID_SCHEMA_ATTR_PASSWORD_BREACH_CHECK: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff0000029a");
+pub const UUID_SCHEMA_ATTR_AUTH_PASSWORD_MAXIMUM_AGE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff0000029b");
+pub const UUID_SCHEMA_ATTR_PASSWORD_LAST_CHANGED: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff0000029c");
+pub const UUID_SCHEMA_ATTR_AUTH_SESSION_IDLE_TIMEOUT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff0000029d");
+pub const UUID_SCHEMA_ATTR_JWT_PUBLIC_KEY: Uuid = uuid!("00000000-0000-0000-0000-ffff0000029e");
 
 // System and domain infos
 // I'd like to strongly criticise william of the past for making poor choices about these allocations.
@@ -451,6 +641,22 @@ pub const UUID_IDM_ACP_APPLICATION_MANAGE: Uuid = uuid!("00000000-0000-0000-0000
 pub const UUID_IDM_ACP_MAIL_SERVERS: Uuid = uuid!("00000000-0000-0000-0000-ffffff000074");
 pub const UUID_SCHEMA_ATTR_OAUTH2_DEVICE_FLOW_ENABLE: Uuid =
     uuid!("00000000-0000-0000-0000-ffffff000075");
+pub const UUID_IDM_ACP_OAUTH2_CLIENT_REGISTER: Uuid =
+    uuid!("00000000-0000-0000-0000-ffffff000076");
+pub const UUID_IDM_ACP_OAUTH2_ENTRY_MANAGER: Uuid = uuid!("00000000-0000-0000-0000-ffffff000077");
+pub const UUID_IDM_ACP_WEBHOOK_MANAGE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000078");
+pub const UUID_IDM_ACP_HBAC_RULE_MANAGE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000079");
+pub const UUID_IDM_ACP_HOST_GROUP_MANAGE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000080");
+pub const UUID_IDM_ACP_HOST_TAG_MANAGE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000081");
+pub const UUID_IDM_ACP_HOST_MANAGE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000082");
+pub const UUID_IDM_ACP_MIGRATION_READ: Uuid = uuid!("00000000-0000-0000-0000-ffffff000083");
+pub const UUID_IDM_ACP_ANNOUNCEMENT_MANAGE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000084");
+pub const UUID_IDM_ACP_OIDC_UPSTREAM_MANAGE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000085");
+pub const UUID_IDM_ACP_WHITE_PAGES_READ: Uuid = uuid!("00000000-0000-0000-0000-ffffff000086");
+pub const UUID_IDM_ACP_SAML2_MANAGE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000087");
+pub const UUID_IDM_ACP_GROUP_MEMBERSHIP_REQUEST_READ: Uuid =
+    uuid!("00000000-0000-0000-0000-ffffff000088");
+pub const UUID_IDM_ACP_HOST_SELF_READ: Uuid = uuid!("00000000-0000-0000-0000-ffffff000089");
 
 // End of system ranges
 pub const UUID_DOES_NOT_EXIST: Uuid = uuid!("00000000-0000-0000-0000-fffffffffffe");
@@ -0,0 +1,194 @@
+//! A bloom filter of the SHA-1 hashes of passwords that are known to have been exposed in
+//! breaches, such as the downloadable dataset from Have I Been Pwned. The dataset is
+//! checked locally, so passwords (or prefixes of their hashes) are never sent to another
+//! service. A bloom filter may report that a password is breached when it is not, but it
+//! never misses a password that was added to it.
+//!
+//! The file format is the magic bytes, the number of hashes as a u32, the number of bits
+//! as a u64, and then the bits as u64 words. All integers are little endian.
+
+use openssl::sha::sha1;
+use std::io::Write;
+
+const BREACH_FILTER_MAGIC: &[u8; 8] = b"KBLOOM01";
+const BREACH_FILTER_HEADER_LEN: usize = 20;
+// Guard against a corrupt header asking for an absurd number of hashes.
+const BREACH_FILTER_MAX_HASHES: u32 = 64;
+
+#[derive(Debug)]
+pub enum PasswordBreachFilterError {
+    InvalidHeader,
+    InvalidLength,
+    InvalidHash,
+}
+
+pub struct PasswordBreachFilter {
+    num_hashes: u32,
+    num_bits: u64,
+    bits: Vec<u64>,
+}
+
+impl std::fmt::Debug for PasswordBreachFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasswordBreachFilter")
+            .field("num_hashes", &self.num_hashes)
+            .field("num_bits", &self.num_bits)
+            .finish()
+    }
+}
+
+impl PasswordBreachFilter {
+    /// Create an empty filter sized to hold this many hashes with the requested rate of
+    /// false positives.
+    pub fn new(items: u64, false_positive_rate: f64) -> Self {
+        let items = items.max(1) as f64;
+        let false_positive_rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let num_bits = (-(items * false_positive_rate.ln()) / (ln2 * ln2)).ceil() as u64;
+        let num_bits = num_bits.max(64);
+        let num_hashes = ((num_bits as f64 / items) * ln2).round() as u32;
+        let num_hashes = num_hashes.clamp(1, BREACH_FILTER_MAX_HASHES);
+
+        PasswordBreachFilter {
+            num_hashes,
+            num_bits,
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+        }
+    }
+
+    /// Load a filter that was previously written with [Self::write_to].
+    pub fn from_bytes(data: &[u8]) -> Result<Self, PasswordBreachFilterError> {
+        let (header, words) = data
+            .split_at_checked(BREACH_FILTER_HEADER_LEN)
+            .ok_or(PasswordBreachFilterError::InvalidHeader)?;
+
+        if &header[..8] != BREACH_FILTER_MAGIC {
+            return Err(PasswordBreachFilterError::InvalidHeader);
+        }
+
+        let mut num_hashes = [0; 4];
+        num_hashes.copy_from_slice(&header[8..12]);
+        let num_hashes = u32::from_le_bytes(num_hashes);
+
+        let mut num_bits = [0; 8];
+        num_bits.copy_from_slice(&header[12..20]);
+        let num_bits = u64::from_le_bytes(num_bits);
+
+        if num_hashes == 0 || num_hashes > BREACH_FILTER_MAX_HASHES || num_bits == 0 {
+            return Err(PasswordBreachFilterError::InvalidHeader);
+        }
+
+        if words.len() as u64 != num_bits.div_ceil(64) * 8 {
+            return Err(PasswordBreachFilterError::InvalidLength);
+        }
+
+        let bits = words
+            .chunks_exact(8)
+            .map(|chunk| {
+                let mut word = [0; 8];
+                word.copy_from_slice(chunk);
+                u64::from_le_bytes(word)
+            })
+            .collect();
+
+        Ok(PasswordBreachFilter {
+            num_hashes,
+            num_bits,
+            bits,
+        })
+    }
+
+    pub fn write_to<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        w.write_all(BREACH_FILTER_MAGIC)?;
+        w.write_all(&self.num_hashes.to_le_bytes())?;
+        w.write_all(&self.num_bits.to_le_bytes())?;
+        for word in self.bits.iter() {
+            w.write_all(&word.to_le_bytes())?;
+        }
+        w.flush()
+    }
+
+    /// The bits for a hash are found by double hashing, using two halves of the digest
+    /// as the independent hash values.
+    fn bit_indexes(&self, digest: &[u8; 20]) -> impl Iterator<Item = u64> + '_ {
+        let mut h1 = [0; 8];
+        h1.copy_from_slice(&digest[0..8]);
+        let h1 = u64::from_le_bytes(h1);
+
+        let mut h2 = [0; 8];
+        h2.copy_from_slice(&digest[8..16]);
+        let h2 = u64::from_le_bytes(h2);
+
+        (0..u64::from(self.num_hashes))
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+
+    pub fn insert_sha1(&mut self, digest: &[u8; 20]) {
+        let indexes: Vec<u64> = self.bit_indexes(digest).collect();
+        for idx in indexes {
+            if let Some(word) = self.bits.get_mut((idx / 64) as usize) {
+                *word |= 1u64 << (idx % 64);
+            }
+        }
+    }
+
+    /// Insert a line of the Have I Been Pwned dataset, which is the hex of the SHA-1 hash
+    /// optionally followed by a colon and the number of times it was seen.
+    pub fn insert_hex_line(&mut self, line: &str) -> Result<(), PasswordBreachFilterError> {
+        let hex_hash = line.split(':').next().unwrap_or_default().trim();
+
+        let decoded = hex::decode(hex_hash).map_err(|_| PasswordBreachFilterError::InvalidHash)?;
+        let digest: [u8; 20] = decoded
+            .try_into()
+            .map_err(|_| PasswordBreachFilterError::InvalidHash)?;
+
+        self.insert_sha1(&digest);
+        Ok(())
+    }
+
+    pub fn contains_sha1(&self, digest: &[u8; 20]) -> bool {
+        self.bit_indexes(digest).all(|idx| {
+            self.bits
+                .get((idx / 64) as usize)
+                .is_some_and(|word| word & (1u64 << (idx % 64)) != 0)
+        })
+    }
+
+    pub fn contains_password(&self, cleartext: &str) -> bool {
+        self.contains_sha1(&sha1(cleartext.as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PasswordBreachFilter;
+    use openssl::sha::sha1;
+
+    #[test]
+    fn test_password_breach_filter() {
+        let mut filter = PasswordBreachFilter::new(1000, 0.001);
+
+        filter.insert_sha1(&sha1(b"correct horse battery staple"));
+        // The hash of "password", as it appears in the breach dataset.
+        filter
+            .insert_hex_line("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004")
+            .expect("Failed to insert hash");
+        assert!(filter.insert_hex_line("not a hash").is_err());
+
+        assert!(filter.contains_password("correct horse battery staple"));
+        assert!(filter.contains_password("password"));
+        assert!(!filter.contains_password("a password that was never breached"));
+
+        let mut data = Vec::new();
+        filter.write_to(&mut data).expect("Failed to write filter");
+
+        let loaded = PasswordBreachFilter::from_bytes(&data).expect("Failed to load filter");
+        assert!(loaded.contains_password("correct horse battery staple"));
+        assert!(loaded.contains_password("password"));
+        assert!(!loaded.contains_password("a password that was never breached"));
+
+        assert!(PasswordBreachFilter::from_bytes(&data[..data.len() - 1]).is_err());
+        assert!(PasswordBreachFilter::from_bytes(b"KBLOOM00").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+//! The password denylist holds terms that are specific to a domain, such as the name of the
+//! organisation, its products or the current season, that passwords may not contain. Unlike
+//! the badlist, which holds complete passwords, a term is denied wherever it appears in a
+//! password. Common character substitutions are undone before checking so that variants of
+//! a term are also denied.
+
+use std::collections::BTreeMap;
+
+/// Undo the substitutions that are commonly used to disguise a word, so that "@cm3" is
+/// treated the same as "acme".
+pub(crate) fn password_denylist_normalise(s: &str) -> String {
+    s.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            '@' | '4' => 'a',
+            '8' => 'b',
+            '3' => 'e',
+            '6' | '9' => 'g',
+            '!' | '1' | '|' | 'l' => 'i',
+            '0' => 'o',
+            '$' | '5' => 's',
+            '7' | '+' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+/// Find the first denied term that the password contains. The denylist maps each term as
+/// it was configured to its normalised form.
+pub(crate) fn password_denylist_match<'a>(
+    denylist: &'a BTreeMap<String, String>,
+    cleartext: &str,
+) -> Option<&'a str> {
+    if denylist.is_empty() {
+        return None;
+    }
+
+    let normalised = password_denylist_normalise(cleartext);
+
+    denylist
+        .iter()
+        .find(|(_, term)| !term.is_empty() && normalised.contains(term.as_str()))
+        .map(|(term, _)| term.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_password_denylist() {
+        let denylist: BTreeMap<String, String> = ["acme", "roadrunner", "summer"]
+            .into_iter()
+            .map(|term| (term.to_string(), password_denylist_normalise(term)))
+            .collect();
+
+        assert_eq!(
+            password_denylist_match(&denylist, "correct horse battery staple"),
+            None
+        );
+        // Terms are matched anywhere in the password, regardless of case.
+        assert_eq!(
+            password_denylist_match(&denylist, "MyAcmePassword"),
+            Some("acme")
+        );
+        assert_eq!(
+            password_denylist_match(&denylist, "Summer2024!"),
+            Some("summer")
+        );
+        // Substitutions don't hide the term.
+        assert_eq!(
+            password_denylist_match(&denylist, "r0@drunn3r_b33p"),
+            Some("roadrunner")
+        );
+        assert_eq!(password_denylist_match(&denylist, "$umm3r"), Some("summer"));
+
+        assert_eq!(password_denylist_match(&BTreeMap::new(), "acme"), None);
+    }
+}
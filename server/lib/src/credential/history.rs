@@ -0,0 +1,136 @@
+//! Password history remembers the previous passwords of an account so that they can't be
+//! reused. Each previous password is stored as a password only credential, tagged with a
+//! sequence number so that the oldest can be discarded once the account policy limit is
+//! reached.
+
+use std::collections::BTreeMap;
+
+use crate::credential::{Credential, Password};
+use crate::prelude::*;
+
+fn history_tag(seq: u64) -> String {
+    format!("{:010}", seq)
+}
+
+/// The most recent `count` passwords from the history, newest first.
+fn recent_passwords(
+    history: Option<&BTreeMap<String, Credential>>,
+    count: u32,
+) -> impl Iterator<Item = &Password> {
+    history
+        .into_iter()
+        .flat_map(|history| history.values().rev())
+        .filter_map(|cred| cred.password_ref().ok())
+        .take(count as usize)
+}
+
+/// Check if a cleartext password matches the current password, or any of the `count` most
+/// recent passwords in the history.
+pub(crate) fn password_history_verify(
+    current: Option<&Credential>,
+    history: Option<&BTreeMap<String, Credential>>,
+    count: u32,
+    cleartext: &str,
+) -> Result<bool, OperationError> {
+    let current = current.and_then(|cred| cred.password_ref().ok());
+
+    for pw in current.into_iter().chain(recent_passwords(history, count)) {
+        if pw.verify(cleartext).map_err(|e| {
+            error!(crypto_err = ?e);
+            OperationError::from(e)
+        })? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Check if a password hash is the same as any of the `count` most recent passwords in the
+/// history. This is only able to detect reuse when the hash was imported in the same format
+/// and with the same salt, such as from the same external system.
+pub(crate) fn password_history_contains(
+    history: Option<&BTreeMap<String, Credential>>,
+    count: u32,
+    pw: &Password,
+) -> bool {
+    recent_passwords(history, count).any(|prev| prev == pw)
+}
+
+/// Add a retired password to the history, returning the values of the new history with only
+/// the `count` most recent passwords kept.
+pub(crate) fn password_history_values(
+    history: Option<&BTreeMap<String, Credential>>,
+    count: u32,
+    retired: &Password,
+) -> Vec<Value> {
+    let next_seq = history
+        .and_then(|history| history.keys().next_back())
+        .and_then(|tag| tag.parse::<u64>().ok())
+        .map(|seq| seq + 1)
+        .unwrap_or(0);
+
+    let mut values: Vec<Value> = history
+        .into_iter()
+        .flat_map(|history| history.iter())
+        .rev()
+        .take((count as usize).saturating_sub(1))
+        .map(|(tag, cred)| Value::new_credential(tag, cred.clone()))
+        .collect();
+
+    if count > 0 {
+        values.push(Value::new_credential(
+            &history_tag(next_seq),
+            Credential::new_from_password(retired.clone()),
+        ));
+    }
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credential::CryptoPolicy;
+
+    #[test]
+    fn test_credential_password_history() {
+        let p = CryptoPolicy::minimum();
+
+        let mut history: BTreeMap<String, Credential> = BTreeMap::new();
+
+        for (i, cleartext) in ["one", "two", "three"].iter().enumerate() {
+            let pw = Password::new(&p, cleartext).expect("Failed to create password");
+            let values = password_history_values(Some(&history), 2, &pw);
+            assert_eq!(values.len(), std::cmp::min(i + 1, 2));
+
+            history = values
+                .into_iter()
+                .filter_map(|v| match v {
+                    Value::Cred(tag, cred) => Some((tag, cred)),
+                    _ => None,
+                })
+                .collect();
+        }
+
+        // Only the two most recent passwords are kept.
+        assert!(!password_history_verify(None, Some(&history), 2, "one").unwrap());
+        assert!(password_history_verify(None, Some(&history), 2, "two").unwrap());
+        assert!(password_history_verify(None, Some(&history), 2, "three").unwrap());
+        // A smaller count only checks the most recent.
+        assert!(!password_history_verify(None, Some(&history), 1, "two").unwrap());
+
+        // The current password is always checked.
+        let current = Credential::new_password_only(&p, "four").unwrap();
+        assert!(password_history_verify(Some(&current), None, 0, "four").unwrap());
+
+        let three = history
+            .values()
+            .next_back()
+            .and_then(|cred| cred.password_ref().ok())
+            .cloned()
+            .unwrap();
+        assert!(password_history_contains(Some(&history), 2, &three));
+        assert!(!password_history_contains(Some(&history), 0, &three));
+    }
+}
@@ -11,6 +11,10 @@ use webauthn_rs_core::proto::{Credential as WebauthnCredential, CredentialV3};
 use crate::be::dbvalue::{DbBackupCodeV1, DbCred};
 
 pub mod apppwd;
+pub mod breach;
+pub mod denylist;
+pub mod history;
+pub mod passphrase;
 pub mod softlock;
 pub mod totp;
 
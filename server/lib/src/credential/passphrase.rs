@@ -0,0 +1,74 @@
+//! Passphrases are made of words chosen at random from a list. They are far easier to remember
+//! than a random password of the same strength, so they are suggested to people when they set
+//! a new password.
+
+use rand::prelude::*;
+
+/// The minimum entropy in bits of a suggested passphrase.
+const PASSPHRASE_MIN_ENTROPY: f64 = 72.0;
+
+const PASSPHRASE_SEPARATOR: &str = "-";
+
+lazy_static! {
+    static ref PASSPHRASE_WORDS: Vec<&'static str> = include_str!("passphrase_words.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .collect();
+}
+
+/// The number of words needed to reach the minimum entropy.
+fn passphrase_word_count() -> usize {
+    let bits_per_word = (PASSPHRASE_WORDS.len() as f64).log2();
+    (PASSPHRASE_MIN_ENTROPY / bits_per_word).ceil() as usize
+}
+
+/// Generate a passphrase that is at least `min_length` characters long. Words are added
+/// beyond those needed for the minimum entropy until the length is reached.
+pub(crate) fn passphrase_from_random(min_length: usize) -> String {
+    let mut rng = thread_rng();
+    let word_count = passphrase_word_count();
+    let mut words: Vec<&str> = Vec::with_capacity(word_count + 1);
+    let mut length = 0;
+
+    while words.len() < word_count || length < min_length {
+        #[allow(clippy::expect_used)]
+        let word = PASSPHRASE_WORDS
+            .choose(&mut rng)
+            .expect("The passphrase word list is empty");
+        if !words.is_empty() {
+            length += PASSPHRASE_SEPARATOR.len();
+        }
+        length += word.len();
+        words.push(word);
+    }
+
+    words.join(PASSPHRASE_SEPARATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_passphrase_from_random() {
+        // The word list must be large enough that words add meaningful entropy.
+        assert!(PASSPHRASE_WORDS.len() >= 1024);
+        assert!(PASSPHRASE_WORDS
+            .iter()
+            .all(|word| word.chars().all(|c| c.is_ascii_lowercase())));
+
+        let passphrase = passphrase_from_random(0);
+        assert_eq!(
+            passphrase.split(PASSPHRASE_SEPARATOR).count(),
+            passphrase_word_count()
+        );
+
+        // Longer minimum lengths add more words.
+        let passphrase = passphrase_from_random(128);
+        assert!(passphrase.len() >= 128);
+        assert!(passphrase.split(PASSPHRASE_SEPARATOR).count() > passphrase_word_count());
+
+        assert_ne!(passphrase_from_random(0), passphrase_from_random(0));
+    }
+}
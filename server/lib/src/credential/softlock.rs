@@ -25,6 +25,11 @@
 //! reset_count_at and a max number of attempts in that window (say 5). with short
 //! delays in between (1 second).
 //!
+//! An account policy may replace these defaults with a lockout. The reset_count_at window
+//! then begins at the first failure, and each further failure doubles the delay until the
+//! threshold is reached. At that point unlock_at and reset_count_at are both set to the end
+//! of the lockout duration, so the count starts again once the lock expires.
+//!
 //! ```text
 //!
 //!                                                  ┌────────────────────────┐
@@ -62,19 +67,31 @@
 use std::time::Duration;
 
 const ONEDAY: u64 = 86400;
+// The delay between attempts under a lockout policy stops doubling at 64 seconds.
+const LOCKOUT_MAX_DELAY_SHIFT: usize = 6;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CredSoftLockPolicy {
     Password,
     Totp(u64),
     Webauthn,
     Unrestricted,
+    Lockout {
+        threshold: u32,
+        window: Duration,
+        duration: Duration,
+    },
 }
 
 impl CredSoftLockPolicy {
     /// Determine the next lock state after a failure based on this credentials
-    /// policy.
-    fn failure_next_state(&self, count: usize, ct: Duration) -> LockState {
+    /// policy. reset_at is the end of the current cycle, if one has begun.
+    fn failure_next_state(
+        &self,
+        count: usize,
+        reset_at: Option<Duration>,
+        ct: Duration,
+    ) -> LockState {
         match self {
             CredSoftLockPolicy::Password => {
                 let next_day_end = ct.as_secs() + ONEDAY;
@@ -119,6 +136,22 @@ impl CredSoftLockPolicy {
                 // No action needed
                 LockState::Init
             }
+            CredSoftLockPolicy::Lockout {
+                threshold,
+                window,
+                duration,
+            } => {
+                if count >= *threshold as usize {
+                    // Lock until the duration passes, then start counting again.
+                    let unlock_at = ct + *duration;
+                    LockState::Locked(count, unlock_at, unlock_at)
+                } else {
+                    // The window is measured from the first failure of the cycle.
+                    let reset_at = reset_at.unwrap_or(ct + *window);
+                    let delay = 1 << (count - 1).min(LOCKOUT_MAX_DELAY_SHIFT);
+                    LockState::Locked(count, reset_at, ct + Duration::from_secs(delay))
+                }
+            }
         }
     }
 }
@@ -181,20 +214,61 @@ impl CredSoftLock {
         !matches!(self.state, LockState::Locked(_count, _reset_at, _unlock_at))
     }
 
+    /// If this credential is locked until the end of the current cycle, the time at
+    /// which it will unlock.
+    pub fn locked_until(&self) -> Option<Duration> {
+        match self.state {
+            LockState::Locked(_count, reset_at, unlock_at) if unlock_at >= reset_at => {
+                Some(reset_at)
+            }
+            _ => None,
+        }
+    }
+
+    /// If this credential is currently denied, the time at which it may next be used.
+    pub fn unlock_at(&self) -> Option<Duration> {
+        match self.state {
+            LockState::Locked(_count, _reset_at, unlock_at) => Some(unlock_at),
+            _ => None,
+        }
+    }
+
+    /// The number of failures in the current cycle.
+    pub fn failure_count(&self) -> usize {
+        match self.state {
+            LockState::Init => 0,
+            LockState::Locked(count, _reset_at, _unlock_at)
+            | LockState::Unlocked(count, _reset_at) => count,
+        }
+    }
+
+    /// Update the policy of this credential, such as when the account policy has changed.
+    /// Failures that have already been recorded are kept.
+    pub fn set_policy(&mut self, policy: CredSoftLockPolicy) {
+        self.policy = policy;
+    }
+
+    /// Forget all failures, allowing the credential to be used immediately.
+    pub fn reset(&mut self) {
+        self.state = LockState::Init;
+    }
+
     /// Document a failure of authentication at this time.
     pub fn record_failure(&mut self, ct: Duration) {
         let mut next_state = match self.state {
             LockState::Init => {
-                self.policy.failure_next_state(1, ct)
+                self.policy.failure_next_state(1, None, ct)
                 // LockState::Locked(1, reset_at, unlock_at)
             }
-            LockState::Locked(count, _reset_at, _unlock_at) => {
+            LockState::Locked(count, reset_at, _unlock_at) => {
                 // We should never reach this but just in case ...
-                self.policy.failure_next_state(count + 1, ct)
+                self.policy
+                    .failure_next_state(count + 1, Some(reset_at), ct)
                 // LockState::Locked(count + 1, reset_at, unlock_at)
             }
-            LockState::Unlocked(count, _reset_at) => {
-                self.policy.failure_next_state(count + 1, ct)
+            LockState::Unlocked(count, reset_at) => {
+                self.policy
+                    .failure_next_state(count + 1, Some(reset_at), ct)
                 // LockState::Locked(count + 1, reset_at, unlock_at)
             }
         };
@@ -294,27 +368,27 @@ mod tests {
         let policy = CredSoftLockPolicy::Password;
 
         assert!(
-            policy.failure_next_state(1, Duration::from_secs(0))
+            policy.failure_next_state(1, None, Duration::from_secs(0))
                 == LockState::Locked(1, Duration::from_secs(ONEDAY), Duration::from_secs(1))
         );
 
         assert!(
-            policy.failure_next_state(8, Duration::from_secs(0))
+            policy.failure_next_state(8, None, Duration::from_secs(0))
                 == LockState::Locked(8, Duration::from_secs(ONEDAY), Duration::from_secs(3))
         );
 
         assert!(
-            policy.failure_next_state(24, Duration::from_secs(0))
+            policy.failure_next_state(24, None, Duration::from_secs(0))
                 == LockState::Locked(24, Duration::from_secs(ONEDAY), Duration::from_secs(5))
         );
 
         assert!(
-            policy.failure_next_state(99, Duration::from_secs(0))
+            policy.failure_next_state(99, None, Duration::from_secs(0))
                 == LockState::Locked(99, Duration::from_secs(ONEDAY), Duration::from_secs(10))
         );
 
         assert!(
-            policy.failure_next_state(100, Duration::from_secs(0))
+            policy.failure_next_state(100, None, Duration::from_secs(0))
                 == LockState::Locked(
                     100,
                     Duration::from_secs(ONEDAY),
@@ -328,7 +402,7 @@ mod tests {
         let policy = CredSoftLockPolicy::Totp(TOTP_DEFAULT_STEP);
 
         assert!(
-            policy.failure_next_state(1, Duration::from_secs(10))
+            policy.failure_next_state(1, None, Duration::from_secs(10))
                 == LockState::Locked(
                     1,
                     Duration::from_secs(TOTP_DEFAULT_STEP),
@@ -337,7 +411,7 @@ mod tests {
         );
 
         assert!(
-            policy.failure_next_state(2, Duration::from_secs(10))
+            policy.failure_next_state(2, None, Duration::from_secs(10))
                 == LockState::Locked(
                     2,
                     Duration::from_secs(TOTP_DEFAULT_STEP),
@@ -346,7 +420,7 @@ mod tests {
         );
 
         assert!(
-            policy.failure_next_state(3, Duration::from_secs(10))
+            policy.failure_next_state(3, None, Duration::from_secs(10))
                 == LockState::Locked(
                     3,
                     Duration::from_secs(TOTP_DEFAULT_STEP),
@@ -360,14 +434,65 @@ mod tests {
         let policy = CredSoftLockPolicy::Webauthn;
 
         assert!(
-            policy.failure_next_state(1, Duration::from_secs(0))
+            policy.failure_next_state(1, None, Duration::from_secs(0))
                 == LockState::Locked(1, Duration::from_secs(1), Duration::from_secs(1))
         );
 
         // No matter how many failures, webauthn always only delays by 1 second.
         assert!(
-            policy.failure_next_state(1000, Duration::from_secs(0))
+            policy.failure_next_state(1000, None, Duration::from_secs(0))
                 == LockState::Locked(1000, Duration::from_secs(1), Duration::from_secs(1))
         );
     }
+
+    #[test]
+    fn test_credential_softlock_policy_lockout() {
+        let policy = CredSoftLockPolicy::Lockout {
+            threshold: 3,
+            window: Duration::from_secs(60),
+            duration: Duration::from_secs(300),
+        };
+
+        // The window begins at the first failure, and the delay doubles.
+        assert_eq!(
+            policy.failure_next_state(1, None, Duration::from_secs(10)),
+            LockState::Locked(1, Duration::from_secs(70), Duration::from_secs(11))
+        );
+
+        assert_eq!(
+            policy.failure_next_state(2, Some(Duration::from_secs(70)), Duration::from_secs(20)),
+            LockState::Locked(2, Duration::from_secs(70), Duration::from_secs(22))
+        );
+
+        // At the threshold, the credential is locked for the duration.
+        assert_eq!(
+            policy.failure_next_state(3, Some(Duration::from_secs(70)), Duration::from_secs(30)),
+            LockState::Locked(3, Duration::from_secs(330), Duration::from_secs(330))
+        );
+
+        let mut slock = CredSoftLock::new(policy);
+        slock.record_failure(Duration::from_secs(10));
+        slock.apply_time_step(Duration::from_secs(12));
+        assert!(slock.is_valid());
+        assert_eq!(slock.locked_until(), None);
+
+        slock.record_failure(Duration::from_secs(12));
+        slock.apply_time_step(Duration::from_secs(15));
+        slock.record_failure(Duration::from_secs(15));
+        assert_eq!(slock.failure_count(), 3);
+        assert_eq!(slock.locked_until(), Some(Duration::from_secs(315)));
+
+        slock.apply_time_step(Duration::from_secs(100));
+        assert!(!slock.is_valid());
+
+        // Once the lock expires the count starts again.
+        slock.apply_time_step(Duration::from_secs(316));
+        assert!(slock.is_state_init());
+
+        // An administrator can clear the lock early.
+        slock.record_failure(Duration::from_secs(320));
+        slock.reset();
+        assert!(slock.is_valid());
+        assert_eq!(slock.failure_count(), 0);
+    }
 }
@@ -27,6 +27,7 @@
 use std::cmp::Ordering;
 pub use std::collections::BTreeSet as Set;
 use std::collections::{BTreeMap as Map, BTreeMap, BTreeSet};
+use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::be::dbentry::{DbEntry, DbEntryVers};
@@ -49,7 +50,7 @@ use kanidm_proto::internal::{
     ConsistencyError, Filter as ProtoFilter, OperationError, SchemaError, UiHint,
 };
 use kanidm_proto::scim_v1::server::ScimEffectiveAccess;
-use kanidm_proto::v1::Entry as ProtoEntry;
+use kanidm_proto::v1::{Entry as ProtoEntry, GroupMemberValidUntil, SshPublicKeyExpiry};
 use ldap3_proto::simple::{LdapPartialAttribute, LdapSearchResultEntry};
 use openssl::ec::EcKey;
 use openssl::pkey::{Private, Public};
@@ -62,10 +63,10 @@ use webauthn_rs::prelude::{
 
 use crate::schema::{SchemaAttribute, SchemaClass, SchemaTransaction};
 use crate::value::{
-    ApiToken, CredentialType, IndexType, IntentTokenState, Oauth2Session, PartialValue, Session,
-    SyntaxType, Value,
+    ApiToken, CredentialFactor, CredentialType, CredentialUsage, IndexType, IntentTokenState,
+    Oauth2Session, PartialValue, Session, SyntaxType, Value,
 };
-use crate::valueset::{self, ScimResolveStatus, ValueSet};
+use crate::valueset::{self, ScimResolveStatus, ValueSet, ValueSetSshKey};
 
 pub type EntryInitNew = Entry<EntryInit, EntryNew>;
 pub type EntryInvalidNew = Entry<EntryInvalid, EntryNew>;
@@ -1871,6 +1872,20 @@ impl Entry<EntrySealed, EntryCommitted> {
             .ecstate
             .verify(schema, &self.attrs, self.state.id, results);
     }
+
+    /// Assert that this entry still conforms to the current schema. Entries are validated
+    /// as they are written, but a schema change may have since invalidated them.
+    pub(crate) fn verify_schema(&self, schema: &dyn SchemaTransaction) -> Result<(), SchemaError> {
+        let ne: Entry<EntryValid, EntryCommitted> = Entry {
+            valid: EntryValid {
+                uuid: self.valid.uuid,
+                ecstate: self.valid.ecstate.clone(),
+            },
+            state: self.state.clone(),
+            attrs: self.attrs.clone(),
+        };
+        ne.validate(schema)
+    }
 }
 
 impl<STATE> Entry<EntryValid, STATE> {
@@ -2260,7 +2275,10 @@ impl Entry<EntryReduced, EntryCommitted> {
     }
 
     /// Transform this reduced entry into a JSON protocol form that can be sent to clients.
-    pub fn to_pe(&self, qs: &mut QueryServerReadTransaction) -> Result<ProtoEntry, OperationError> {
+    pub fn to_pe<'a, TXN>(&self, qs: &mut TXN) -> Result<ProtoEntry, OperationError>
+    where
+        TXN: QueryServerTransaction<'a>,
+    {
         // Turn values -> Strings.
         let attrs: Result<_, _> = self
             .attrs
@@ -2346,11 +2364,21 @@ impl Entry<EntryReduced, EntryCommitted> {
         // Did the ldap client request any sperific attribute names? If so,
         // we need to remap everything to match.
         l_attrs: &[String],
+        ct: Duration,
     ) -> Result<LdapSearchResultEntry, OperationError> {
         let rdn = qs.uuid_to_rdn(self.get_uuid())?;
 
         let dn = format!("{rdn},{basedn}");
 
+        // Expired ssh public keys are not distributed to clients. The expiry may have been
+        // reduced away by access controls, so it's checked on the full entry.
+        let expired_sshkeys = if self.attribute_pres(Attribute::SshPublicKey) {
+            qs.internal_search_uuid(self.get_uuid())?
+                .get_sshpubkeys_expired(ct)
+        } else {
+            BTreeSet::new()
+        };
+
         // Everything in our attrs set is "what was requested". So we can transform that now
         // so they are all in "ldap forms" which makes our next stage a bit easier.
 
@@ -2359,8 +2387,21 @@ impl Entry<EntryReduced, EntryCommitted> {
             .attrs
             .iter()
             .map(|(k, vs)| {
-                qs.resolve_valueset_ldap(vs, basedn)
-                    .map(|pvs| (k.as_str(), pvs))
+                if k == &Attribute::SshPublicKey && !expired_sshkeys.is_empty() {
+                    let unexpired: ValueSet = ValueSetSshKey::from_iter(
+                        vs.as_sshkey_map()
+                            .into_iter()
+                            .flatten()
+                            .filter(|(tag, _)| !expired_sshkeys.contains(*tag))
+                            .map(|(tag, pk)| (tag.clone(), pk.clone())),
+                    )
+                    .ok_or(OperationError::InvalidValueState)?;
+                    qs.resolve_valueset_ldap(&unexpired, basedn)
+                        .map(|pvs| (k.as_str(), pvs))
+                } else {
+                    qs.resolve_valueset_ldap(vs, basedn)
+                        .map(|pvs| (k.as_str(), pvs))
+                }
             })
             .collect();
         let attr_map = attr_map?;
@@ -2588,6 +2629,14 @@ impl<VALID, STATE> Entry<VALID, STATE> {
         self.get_ava_set(attr).and_then(|vs| vs.as_apitoken_map())
     }
 
+    pub fn get_ava_as_credential_usage_map<A: AsRef<Attribute>>(
+        &self,
+        attr: A,
+    ) -> Option<&std::collections::BTreeMap<(Uuid, CredentialFactor), CredentialUsage>> {
+        self.get_ava_set(attr)
+            .and_then(|vs| vs.as_credential_usage_map())
+    }
+
     pub fn get_ava_as_oauth2session_map<A: AsRef<Attribute>>(
         &self,
         attr: A,
@@ -2630,6 +2679,46 @@ impl<VALID, STATE> Entry<VALID, STATE> {
             .and_then(|vs| vs.as_sshpubkey_string_iter())
     }
 
+    /// The labels of the ssh public keys of this entry that have expired at the current time.
+    pub fn get_sshpubkeys_expired(&self, ct: Duration) -> BTreeSet<String> {
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+        self.get_ava_set(Attribute::SshPublicKeyExpiry)
+            .and_then(|vs| vs.as_utf8_iter())
+            .into_iter()
+            .flatten()
+            .filter_map(|expiry| SshPublicKeyExpiry::from_str(expiry).ok())
+            .filter(|expiry| expiry.expiry <= now)
+            .map(|expiry| expiry.label)
+            .collect()
+    }
+
+    /// The times that the memberships of the time-bound members of this group lapse.
+    pub fn get_member_valid_until(&self) -> BTreeMap<Uuid, OffsetDateTime> {
+        self.get_ava_set(Attribute::MemberValidUntil)
+            .and_then(|vs| vs.as_utf8_iter())
+            .into_iter()
+            .flatten()
+            .filter_map(|valid_until| {
+                GroupMemberValidUntil::from_str(valid_until)
+                    .inspect_err(|_| {
+                        warn!(?valid_until, "Ignoring invalid group member valid until");
+                    })
+                    .ok()
+            })
+            .map(|valid_until| (valid_until.member, valid_until.valid_until))
+            .collect()
+    }
+
+    /// The members of this group whose membership has lapsed at the current time.
+    pub fn get_members_expired(&self, ct: Duration) -> BTreeSet<Uuid> {
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+        self.get_member_valid_until()
+            .into_iter()
+            .filter(|(_, valid_until)| *valid_until <= now)
+            .map(|(member, _)| member)
+            .collect()
+    }
+
     // These are special types to allow returning typed values from
     // an entry, if we "know" what we expect to receive.
 
@@ -2681,6 +2770,14 @@ impl<VALID, STATE> Entry<VALID, STATE> {
             .and_then(|vs| vs.to_credential_single())
     }
 
+    /// Get the set of credentials of this attribute by their tag, if any are present.
+    pub fn get_ava_as_credential_map<A: AsRef<Attribute>>(
+        &self,
+        attr: A,
+    ) -> Option<&BTreeMap<String, Credential>> {
+        self.get_ava_set(attr).and_then(|vs| vs.as_credential_map())
+    }
+
     /// Get the set of passkeys on this account, if any are present.
     pub fn get_ava_passkeys<A: AsRef<Attribute>>(
         &self,
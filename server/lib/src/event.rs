@@ -804,7 +804,6 @@ impl ReviveRecycledEvent {
         }
     }
 
-    #[cfg(test)]
     pub(crate) fn new_internal(filter: Filter<FilterValid>) -> Self {
         ReviveRecycledEvent {
             ident: Identity::from_internal(),
@@ -24,12 +24,12 @@ use hashbrown::HashSet;
 use kanidm_proto::constants::ATTR_UUID;
 use kanidm_proto::internal::{Filter as ProtoFilter, OperationError, SchemaError};
 use kanidm_proto::scim_v1::client::{AttrPath as ScimAttrPath, ScimFilter};
-use ldap3_proto::proto::{LdapFilter, LdapSubstringFilter};
+use ldap3_proto::proto::{LdapFilter, LdapMatchingRuleAssertion, LdapSubstringFilter};
 use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::be::{IdxKey, IdxKeyRef, IdxKeyToRef, IdxMeta, IdxSlope};
-use crate::idm::ldap::ldap_attr_filter_map;
+use crate::idm::ldap::{ldap_attr_filter_map, LDAP_MATCHING_RULE_IN_CHAIN};
 use crate::prelude::*;
 use crate::schema::SchemaTransaction;
 use crate::value::{IndexType, PartialValue};
@@ -420,6 +420,39 @@ impl fmt::Display for FilterPlan {
     }
 }
 
+impl FilterPlan {
+    /// Count the terms of this plan that were resolved by an index, and collect the terms that
+    /// had no index available. Corrupt indexes are not counted, since these are resolved by a
+    /// reindex rather than a new index.
+    pub(crate) fn index_usage(&self, hits: &mut usize, misses: &mut Vec<(Attribute, IndexType)>) {
+        match self {
+            Self::EqIndexed(..) | Self::SubIndexed(..) | Self::PresIndexed(_) => *hits += 1,
+            Self::EqUnindexed(attr) => misses.push((attr.clone(), IndexType::Equality)),
+            Self::SubUnindexed(attr) => misses.push((attr.clone(), IndexType::SubString)),
+            Self::PresUnindexed(attr) => misses.push((attr.clone(), IndexType::Presence)),
+            Self::Invalid
+            | Self::EqCorrupt(_)
+            | Self::SubCorrupt(_)
+            | Self::PresCorrupt(_)
+            | Self::LessThanUnindexed(_) => {}
+            Self::OrUnindexed(plan)
+            | Self::OrIndexed(plan)
+            | Self::OrPartial(plan)
+            | Self::OrPartialThreshold(plan)
+            | Self::AndEmptyCand(plan)
+            | Self::AndIndexed(plan)
+            | Self::AndUnindexed(plan)
+            | Self::AndPartial(plan)
+            | Self::AndPartialThreshold(plan)
+            | Self::InclusionInvalid(plan)
+            | Self::InclusionIndexed(plan) => {
+                plan.iter().for_each(|item| item.index_usage(hits, misses))
+            }
+            Self::AndNot(plan) => plan.index_usage(hits, misses),
+        }
+    }
+}
+
 /// A `Filter` is a logical set of assertions about the state of an [`Entry`] and
 /// it's avas. `Filter`s are built from a set of possible assertions.
 ///
@@ -759,9 +792,10 @@ impl Filter<FilterInvalid> {
     ) -> Result<Self, OperationError> {
         let depth = DEFAULT_LIMIT_FILTER_DEPTH_MAX as usize;
         let mut elems = ev.limits().filter_max_elements;
+        let group_compat = qs.get_domain_ldap_group_compat()?;
         Ok(Filter {
             state: FilterInvalid {
-                inner: FilterComp::from_ldap_ro(f, qs, depth, &mut elems)?,
+                inner: FilterComp::from_ldap_ro(f, qs, group_compat, depth, &mut elems)?,
             },
         })
     }
@@ -1099,6 +1133,7 @@ impl FilterComp {
     fn from_ldap_ro(
         f: &LdapFilter,
         qs: &mut QueryServerReadTransaction,
+        group_compat: bool,
         depth: usize,
         elems: &mut usize,
     ) -> Result<Self, OperationError> {
@@ -1109,22 +1144,29 @@ impl FilterComp {
         Ok(match f {
             LdapFilter::And(l) => FilterComp::And(
                 l.iter()
-                    .map(|f| Self::from_ldap_ro(f, qs, ndepth, elems))
+                    .map(|f| Self::from_ldap_ro(f, qs, group_compat, ndepth, elems))
                     .collect::<Result<Vec<_>, _>>()?,
             ),
             LdapFilter::Or(l) => FilterComp::Or(
                 l.iter()
-                    .map(|f| Self::from_ldap_ro(f, qs, ndepth, elems))
+                    .map(|f| Self::from_ldap_ro(f, qs, group_compat, ndepth, elems))
                     .collect::<Result<Vec<_>, _>>()?,
             ),
-            LdapFilter::Not(l) => {
-                FilterComp::AndNot(Box::new(Self::from_ldap_ro(l, qs, ndepth, elems)?))
-            }
+            LdapFilter::Not(l) => FilterComp::AndNot(Box::new(Self::from_ldap_ro(
+                l,
+                qs,
+                group_compat,
+                ndepth,
+                elems,
+            )?)),
             LdapFilter::Equality(a, v) => {
                 let a = ldap_attr_filter_map(a);
                 let pv = qs.clone_partialvalue(&a, v);
 
                 match pv {
+                    Ok(PartialValue::Refer(u)) if group_compat && a == Attribute::Member => {
+                        Self::from_ldap_nested_member(qs, u)?
+                    }
                     Ok(pv) => FilterComp::Eq(a, pv),
                     Err(_) if a == Attribute::Spn => FilterComp::Invalid(a),
                     Err(err) => return Err(err),
@@ -1171,6 +1213,27 @@ impl FilterComp {
                 admin_error!("Unsupported filter operation - approximate");
                 return Err(OperationError::FilterGeneration);
             }
+            LdapFilter::Extensible(LdapMatchingRuleAssertion {
+                matching_rule: Some(rule),
+                type_: Some(a),
+                match_value,
+                ..
+            }) if group_compat && rule == LDAP_MATCHING_RULE_IN_CHAIN => {
+                let a = ldap_attr_filter_map(a);
+                let pv = qs.clone_partialvalue(&a, match_value)?;
+
+                match (a, pv) {
+                    (Attribute::Member, PartialValue::Refer(u)) => {
+                        Self::from_ldap_nested_member(qs, u)?
+                    }
+                    // MemberOf already contains the nested groups of an entry.
+                    (Attribute::MemberOf, pv) => FilterComp::Eq(Attribute::MemberOf, pv),
+                    (a, _) => {
+                        admin_error!(?a, "Unsupported filter operation - in chain");
+                        return Err(OperationError::FilterGeneration);
+                    }
+                }
+            }
             LdapFilter::Extensible(_) => {
                 admin_error!("Unsupported filter operation - extensible");
                 return Err(OperationError::FilterGeneration);
@@ -1178,6 +1241,30 @@ impl FilterComp {
         })
     }
 
+    /// Match the groups that have this entry as a member, either directly or through any
+    /// number of nested groups. As memberof is already the full set of groups the entry is
+    /// within, a group matches if it has the entry or any of these groups as a member.
+    fn from_ldap_nested_member(
+        qs: &mut QueryServerReadTransaction,
+        member: Uuid,
+    ) -> Result<Self, OperationError> {
+        let groups = match qs.internal_search_uuid(member) {
+            Ok(entry) => entry
+                .get_ava_refer(Attribute::MemberOf)
+                .cloned()
+                .unwrap_or_default(),
+            Err(OperationError::NoMatchingEntries) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(FilterComp::Or(
+            iter::once(member)
+                .chain(groups)
+                .map(|u| FilterComp::Eq(Attribute::Member, PartialValue::Refer(u)))
+                .collect(),
+        ))
+    }
+
     fn from_scim_ro(
         f: &ScimFilter,
         qs: &mut QueryServerReadTransaction,
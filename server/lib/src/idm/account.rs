@@ -1,10 +1,13 @@
 use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
 use std::time::Duration;
 
 use kanidm_proto::internal::{
-    BackupCodesView, CredentialStatus, UatPurpose, UiHint, UserAuthToken,
+    BackupCodesView, CredentialStatus, CredentialUsageDetail, UatPurpose, UiHint, UserAuthToken,
+};
+use kanidm_proto::v1::{
+    SshPublicKeyExpiry, UatStatus, UatStatusState, UnixGroupToken, UnixHbacPolicy, UnixUserToken,
 };
-use kanidm_proto::v1::{UatStatus, UatStatusState, UnixGroupToken, UnixUserToken};
 use time::OffsetDateTime;
 use uuid::Uuid;
 use webauthn_rs::prelude::{
@@ -24,7 +27,9 @@ use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTrans
 use crate::modify::{ModifyInvalid, ModifyList};
 use crate::prelude::*;
 use crate::schema::SchemaTransaction;
-use crate::value::{IntentTokenState, PartialValue, SessionState, Value};
+use crate::value::{
+    CredentialFactor, CredentialUsage, IntentTokenState, PartialValue, SessionState, Value,
+};
 use kanidm_lib_crypto::CryptoPolicy;
 use sshkey_attest::proto::PublicKey as SshPublicKey;
 
@@ -33,6 +38,7 @@ pub struct UnixExtensions {
     ucred: Option<Credential>,
     shell: Option<String>,
     gidnumber: u32,
+    home_quota: Option<u32>,
     groups: Vec<Group<Unix>>,
 }
 
@@ -64,7 +70,12 @@ pub struct Account {
     pub credential_update_intent_tokens: BTreeMap<String, IntentTokenState>,
     pub(crate) unix_extn: Option<UnixExtensions>,
     pub(crate) sshkeys: BTreeMap<String, SshPublicKey>,
+    pub(crate) sshkey_expiry: BTreeMap<String, OffsetDateTime>,
+    pub(crate) jwt_public_keys: BTreeMap<String, Vec<u8>>,
     pub apps_pwds: BTreeMap<Uuid, Vec<ApplicationPassword>>,
+    pub(crate) credential_usage: BTreeMap<(Uuid, CredentialFactor), CredentialUsage>,
+    pub(crate) password_history: BTreeMap<String, Credential>,
+    pub(crate) password_last_changed: Option<OffsetDateTime>,
 }
 
 macro_rules! try_from_entry {
@@ -155,6 +166,21 @@ macro_rules! try_from_entry {
             .cloned()
             .unwrap_or_default();
 
+        let sshkey_expiry = $value
+            .get_ava_set(Attribute::SshPublicKeyExpiry)
+            .and_then(|vs| vs.as_utf8_iter())
+            .into_iter()
+            .flatten()
+            .filter_map(|expiry| SshPublicKeyExpiry::from_str(expiry).ok())
+            .map(|expiry| (expiry.label, expiry.expiry))
+            .collect();
+
+        let jwt_public_keys = $value
+            .get_ava_set(Attribute::JwtPublicKey)
+            .and_then(|vs| vs.as_publicbinary_map())
+            .cloned()
+            .unwrap_or_default();
+
         let unix_extn = if $value.attribute_equality(
             Attribute::Class,
             &EntryClass::PosixAccount.to_partialvalue(),
@@ -173,12 +199,15 @@ macro_rules! try_from_entry {
                 .get_ava_single_uint32(Attribute::GidNumber)
                 .ok_or_else(|| OperationError::MissingAttribute(Attribute::GidNumber))?;
 
+            let home_quota = $value.get_ava_single_uint32(Attribute::PosixHomeQuota);
+
             let groups = $unix_groups;
 
             Some(UnixExtensions {
                 ucred,
                 shell,
                 gidnumber,
+                home_quota,
                 groups,
             })
         } else {
@@ -190,6 +219,18 @@ macro_rules! try_from_entry {
             .cloned()
             .unwrap_or_default();
 
+        let credential_usage = $value
+            .get_ava_as_credential_usage_map(Attribute::CredentialUsage)
+            .cloned()
+            .unwrap_or_default();
+
+        let password_history = $value
+            .get_ava_as_credential_map(Attribute::PasswordHistory)
+            .cloned()
+            .unwrap_or_default();
+
+        let password_last_changed = $value.get_ava_single_datetime(Attribute::PasswordLastChanged);
+
         Ok(Account {
             uuid,
             name,
@@ -209,7 +250,12 @@ macro_rules! try_from_entry {
             credential_update_intent_tokens,
             unix_extn,
             sshkeys,
+            sshkey_expiry,
+            jwt_public_keys,
             apps_pwds,
+            credential_usage,
+            password_history,
+            password_last_changed,
         })
     }};
 }
@@ -227,6 +273,18 @@ impl Account {
         &self.sshkeys
     }
 
+    /// Remove the groups that this account's membership has lapsed in at the current time.
+    /// These are removed from memberof by a scheduled task, so this ensures that claims
+    /// issued in the meantime don't include them.
+    pub(crate) fn remove_lapsed_groups(&mut self, ct: Duration) {
+        self.groups.retain(|group| !group.membership_lapsed(ct));
+        if let Some(unix_extn) = self.unix_extn.as_mut() {
+            unix_extn
+                .groups
+                .retain(|group| !group.membership_lapsed(ct));
+        }
+    }
+
     #[instrument(level = "trace", skip_all)]
     pub(crate) fn try_from_entry_ro(
         value: &Entry<EntrySealed, EntryCommitted>,
@@ -334,6 +392,9 @@ impl Account {
             // groups: self.groups.iter().map(|g| g.to_proto()).collect(),
             limit_search_max_results,
             limit_search_max_filter_test,
+            impersonated_by: None,
+            session_key: None,
+            idle_timeout: account_policy.authsession_idle_timeout(),
         })
     }
 
@@ -393,6 +454,9 @@ impl Account {
             // groups: self.groups.iter().map(|g| g.to_proto()).collect(),
             limit_search_max_results,
             limit_search_max_filter_test,
+            impersonated_by: None,
+            session_key: None,
+            idle_timeout: account_policy.authsession_idle_timeout(),
         })
     }
 
@@ -430,6 +494,9 @@ impl Account {
             // groups: self.groups.iter().map(|g| g.to_proto()).collect(),
             limit_search_max_results,
             limit_search_max_filter_test,
+            impersonated_by: None,
+            session_key: None,
+            idle_timeout: None,
         })
     }
 
@@ -467,6 +534,15 @@ impl Account {
         Self::check_within_valid_time(ct, self.valid_from.as_ref(), self.expire.as_ref())
     }
 
+    /// If the password of the account is older than the maximum age. A password that was set
+    /// before the time it was changed was recorded has an unknown age, and is expired.
+    pub(crate) fn password_expired(&self, max_age: Duration, ct: Duration) -> bool {
+        match self.password_last_changed {
+            Some(changed) => changed + max_age <= OffsetDateTime::UNIX_EPOCH + ct,
+            None => true,
+        }
+    }
+
     /// Get related inputs, such as account name, email, etc. This is used for password
     /// quality checking.
     pub fn related_inputs(&self) -> Vec<&str> {
@@ -645,13 +721,46 @@ impl Account {
 
     pub(crate) fn to_credentialstatus(&self) -> Result<CredentialStatus, OperationError> {
         // In the future this will need to handle multiple credentials, not just single.
+        let creds: Vec<_> = self.primary.iter().map(|cred| cred.into()).collect();
 
-        self.primary
-            .as_ref()
-            .map(|cred| CredentialStatus {
-                creds: vec![cred.into()],
+        if creds.is_empty() && self.passkeys.is_empty() && self.attested_passkeys.is_empty() {
+            return Err(OperationError::NoMatchingAttributes);
+        }
+
+        Ok(CredentialStatus {
+            creds,
+            usage: self.to_credential_usage_detail(),
+        })
+    }
+
+    /// Describe when each credential of this account was last used. Usage of credentials that
+    /// have since been removed is not shown.
+    fn to_credential_usage_detail(&self) -> Vec<CredentialUsageDetail> {
+        self.credential_usage
+            .iter()
+            .filter_map(|((cred_id, factor), usage)| {
+                let factor = if self.primary.as_ref().map(|cred| cred.uuid) == Some(*cred_id) {
+                    factor.to_string()
+                } else if let Some(label) = self
+                    .passkeys
+                    .get(cred_id)
+                    .map(|(label, _)| label)
+                    .or_else(|| self.attested_passkeys.get(cred_id).map(|(label, _)| label))
+                {
+                    format!("{} ({})", factor, label)
+                } else {
+                    return None;
+                };
+
+                Some(CredentialUsageDetail {
+                    uuid: *cred_id,
+                    factor,
+                    last_used: usage.last_used,
+                    use_count: usage.use_count,
+                    source: usage.source.clone(),
+                })
             })
-            .ok_or(OperationError::NoMatchingAttributes)
+            .collect()
     }
 
     pub(crate) fn to_backupcodesview(&self) -> Result<BackupCodesView, OperationError> {
@@ -667,6 +776,15 @@ impl Account {
         None
     }
 
+    /// How often the use of a session is recorded. Sessions that have an idle timeout are
+    /// recorded more often, so that a session in use is never considered idle.
+    pub fn session_use_granularity(idle_timeout: Option<u32>) -> u64 {
+        idle_timeout
+            .map(|idle_timeout| u64::from(idle_timeout / 4).max(60))
+            .unwrap_or(SESSION_LAST_USED_GRANULARITY)
+            .min(SESSION_LAST_USED_GRANULARITY)
+    }
+
     pub(crate) fn check_user_auth_token_valid(
         ct: Duration,
         uat: &UserAuthToken,
@@ -793,11 +911,64 @@ impl Account {
         Ok(ModifyList::new_append(Attribute::ApplicationPassword, vap))
     }
 
-    pub(crate) fn to_unixusertoken(&self, ct: Duration) -> Result<UnixUserToken, OperationError> {
-        let (gidnumber, shell, sshkeys, groups) = match &self.unix_extn {
+    /// Substitute the attributes of this account into a posix template from the account
+    /// policy. Unknown placeholders are left as they are.
+    fn render_posix_template(&self, template: &str, gidnumber: u32) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+
+            match &rest[1..end] {
+                "name" => rendered.push_str(&self.name),
+                "spn" => rendered.push_str(&self.spn),
+                "uuid" => rendered.push_str(&self.uuid.hyphenated().to_string()),
+                "displayname" => rendered.push_str(&self.displayname),
+                "gidnumber" => rendered.push_str(&gidnumber.to_string()),
+                _ => rendered.push_str(&rest[..=end]),
+            }
+
+            rest = &rest[end + 1..];
+        }
+
+        rendered.push_str(rest);
+        rendered
+    }
+
+    pub(crate) fn to_unixusertoken(
+        &self,
+        account_policy: &ResolvedAccountPolicy,
+        hbac: Option<UnixHbacPolicy>,
+        login_hosts: Option<Vec<String>>,
+        ct: Duration,
+    ) -> Result<UnixUserToken, OperationError> {
+        let (gidnumber, shell, home_quota, sshkeys, groups) = match &self.unix_extn {
             Some(ue) => {
-                let sshkeys: Vec<_> = self.sshkeys.values().cloned().collect();
-                (ue.gidnumber, ue.shell.clone(), sshkeys, ue.groups.clone())
+                // Expired keys are no longer distributed to clients.
+                let now = OffsetDateTime::UNIX_EPOCH + ct;
+                let sshkeys: Vec<_> = self
+                    .sshkeys
+                    .iter()
+                    .filter(|(tag, _)| {
+                        self.sshkey_expiry
+                            .get(*tag)
+                            .map_or(true, |expiry| *expiry > now)
+                    })
+                    .map(|(_, pk)| pk.clone())
+                    .collect();
+                (
+                    ue.gidnumber,
+                    ue.shell.clone(),
+                    ue.home_quota,
+                    sshkeys,
+                    ue.groups.clone(),
+                )
             }
             None => {
                 return Err(OperationError::MissingClass(
@@ -808,15 +979,32 @@ impl Account {
 
         let groups: Vec<UnixGroupToken> = groups.iter().map(|g| g.to_unixgrouptoken()).collect();
 
+        // The shell of the account takes precedence over the default of the account policy.
+        let shell = shell.or_else(|| account_policy.posix_default_shell().map(str::to_string));
+
+        let home_directory = account_policy
+            .posix_home_template()
+            .map(|template| self.render_posix_template(template, gidnumber));
+
+        let gecos = account_policy
+            .posix_gecos_format()
+            .map(|template| self.render_posix_template(template, gidnumber));
+
         Ok(UnixUserToken {
             name: self.name.clone(),
             spn: self.spn.clone(),
             displayname: self.displayname.clone(),
             gidnumber,
             uuid: self.uuid,
-            shell: shell.clone(),
+            shell,
+            home_directory,
+            home_skeleton: account_policy.posix_home_skeleton().map(str::to_string),
+            home_quota,
+            gecos,
             groups,
             sshkeys,
+            hbac,
+            login_hosts,
             valid: self.is_within_valid_time(ct),
         })
     }
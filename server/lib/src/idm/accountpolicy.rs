@@ -1,18 +1,127 @@
+use crate::credential::softlock::CredSoftLockPolicy;
 use crate::prelude::*;
 use crate::value::CredentialType;
+use kanidm_proto::internal::{AccountPolicyLockout, AccountPolicyNetwork, EffectiveAccountPolicy};
+use sshkey_attest::proto::PublicKey as SshPublicKey;
+use sshkeys::PublicKeyKind;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
 use webauthn_rs::prelude::AttestationCaList;
 
+/// A network that authentication is trusted from, parsed from CIDR notation such as
+/// `10.0.0.0/8` or `2001:db8::/32`. An address without a prefix is a single host.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TrustedNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for TrustedNetwork {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match value.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value.trim(), None),
+        };
+
+        let addr = IpAddr::from_str(addr).map_err(|_| ())?;
+
+        let max_prefix = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        let prefix = match prefix {
+            Some(prefix) => u8::from_str(prefix).map_err(|_| ())?,
+            None => max_prefix,
+        };
+
+        if prefix > max_prefix {
+            return Err(());
+        }
+
+        Ok(TrustedNetwork { addr, prefix })
+    }
+}
+
+impl fmt::Display for TrustedNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl TrustedNetwork {
+    pub(crate) fn contains(&self, ip: &IpAddr) -> bool {
+        // Clients connecting over ipv6 sockets may present as ipv4 mapped addresses.
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The minimum credential type that is required when authenticating from outside of
+/// a set of trusted networks.
+#[derive(Clone, Debug)]
+struct NetworkPolicy {
+    trusted_networks: Vec<TrustedNetwork>,
+    credential_policy: CredentialType,
+}
+
+impl NetworkPolicy {
+    fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.trusted_networks.iter().any(|net| net.contains(ip))
+    }
+}
+
+/// Lock a credential once a number of failed authentication attempts occur within a window.
+/// The window and duration are in seconds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct LockoutPolicy {
+    threshold: u32,
+    window: u32,
+    duration: u32,
+}
+
 #[derive(Clone)]
 #[cfg_attr(test, derive(Default))]
 pub(crate) struct AccountPolicy {
     privilege_expiry: u32,
     authsession_expiry: u32,
     pw_min_length: u32,
+    pw_history_count: u32,
     credential_policy: CredentialType,
     webauthn_att_ca_list: Option<AttestationCaList>,
     limit_search_max_filter_test: Option<u64>,
     limit_search_max_results: Option<u64>,
     allow_primary_cred_fallback: Option<bool>,
+    allow_unix_password: Option<bool>,
+    allow_api_tokens: Option<bool>,
+    allow_email_link: Option<bool>,
+    password_breach_check: Option<bool>,
+    pw_max_age: Option<u32>,
+    authsession_idle_timeout: Option<u32>,
+    login_host_tags: Option<BTreeSet<String>>,
+    ssh_key_allowed_types: Option<BTreeSet<String>>,
+    ssh_key_rsa_min_bits: u32,
+    posix_default_shell: Option<String>,
+    posix_home_template: Option<String>,
+    posix_gecos_format: Option<String>,
+    posix_home_skeleton: Option<String>,
+    network_policy: Option<NetworkPolicy>,
+    lockout_policy: Option<LockoutPolicy>,
+    account_lifetime: Option<u32>,
+    account_archive_delay: Option<u32>,
 }
 
 impl From<&EntrySealedCommitted> for Option<AccountPolicy> {
@@ -36,6 +145,10 @@ impl From<&EntrySealedCommitted> for Option<AccountPolicy> {
             .get_ava_single_uint32(Attribute::AuthPasswordMinimumLength)
             .unwrap_or(PW_MIN_LENGTH);
 
+        let pw_history_count = val
+            .get_ava_single_uint32(Attribute::AuthPasswordHistoryCount)
+            .unwrap_or(0);
+
         let credential_policy = val
             .get_ava_single_credential_type(Attribute::CredentialTypeMinimum)
             .unwrap_or(CredentialType::Any);
@@ -55,15 +168,122 @@ impl From<&EntrySealedCommitted> for Option<AccountPolicy> {
         let allow_primary_cred_fallback =
             val.get_ava_single_bool(Attribute::AllowPrimaryCredFallback);
 
+        let allow_unix_password = val.get_ava_single_bool(Attribute::AllowUnixPassword);
+
+        let allow_api_tokens = val.get_ava_single_bool(Attribute::AllowApiTokens);
+
+        let allow_email_link = val.get_ava_single_bool(Attribute::AllowEmailLink);
+
+        let password_breach_check = val.get_ava_single_bool(Attribute::PasswordBreachCheck);
+
+        let pw_max_age = val.get_ava_single_uint32(Attribute::AuthPasswordMaximumAge);
+
+        let authsession_idle_timeout = val.get_ava_single_uint32(Attribute::AuthSessionIdleTimeout);
+
+        let login_host_tags = val
+            .get_ava_iter_iutf8(Attribute::LoginHostTag)
+            .map(|iter| iter.map(str::to_string).collect());
+
+        let ssh_key_allowed_types = val
+            .get_ava_iter_iutf8(Attribute::SshKeyAllowedType)
+            .map(|iter| iter.map(str::to_string).collect());
+
+        let ssh_key_rsa_min_bits = val
+            .get_ava_single_uint32(Attribute::SshKeyRsaMinimumBits)
+            .unwrap_or(0);
+
+        let posix_default_shell = val
+            .get_ava_single_utf8(Attribute::PosixDefaultShell)
+            .map(str::to_string);
+
+        let posix_home_template = val
+            .get_ava_single_utf8(Attribute::PosixHomeTemplate)
+            .map(str::to_string);
+
+        let posix_gecos_format = val
+            .get_ava_single_utf8(Attribute::PosixGecosFormat)
+            .map(str::to_string);
+
+        let posix_home_skeleton = val
+            .get_ava_single_utf8(Attribute::PosixHomeSkeleton)
+            .map(str::to_string);
+
+        // An invalid network is ignored, which can only make the policy stricter.
+        let trusted_networks: Vec<TrustedNetwork> = val
+            .get_ava_set(Attribute::AuthTrustedNetwork)
+            .and_then(|vs| vs.as_utf8_iter())
+            .map(|iter| {
+                iter.filter_map(|net| {
+                    TrustedNetwork::from_str(net)
+                        .map_err(|_| {
+                            warn!(?net, "Ignoring invalid trusted network in account policy");
+                        })
+                        .ok()
+                })
+                .collect()
+            })
+            .unwrap_or_default();
+
+        let untrusted_credential_policy =
+            val.get_ava_single_credential_type(Attribute::CredentialTypeMinimumUntrustedNetwork);
+
+        // If trusted networks are defined without a credential type, then authentication
+        // from any other network is denied.
+        let network_policy = if trusted_networks.is_empty() && untrusted_credential_policy.is_none()
+        {
+            None
+        } else {
+            Some(NetworkPolicy {
+                trusted_networks,
+                credential_policy: untrusted_credential_policy.unwrap_or(CredentialType::Invalid),
+            })
+        };
+
+        // Lockout is only enabled by a threshold, the window and duration have defaults.
+        let lockout_policy = val
+            .get_ava_single_uint32(Attribute::AuthLockoutThreshold)
+            .filter(|threshold| *threshold > 0)
+            .map(|threshold| LockoutPolicy {
+                threshold,
+                window: val
+                    .get_ava_single_uint32(Attribute::AuthLockoutWindow)
+                    .unwrap_or(DEFAULT_AUTH_LOCKOUT_WINDOW),
+                duration: val
+                    .get_ava_single_uint32(Attribute::AuthLockoutDuration)
+                    .unwrap_or(DEFAULT_AUTH_LOCKOUT_DURATION),
+            });
+
+        let account_lifetime = val.get_ava_single_uint32(Attribute::AccountLifetime);
+
+        let account_archive_delay = val.get_ava_single_uint32(Attribute::AccountArchiveDelay);
+
         Some(AccountPolicy {
             privilege_expiry,
             authsession_expiry,
             pw_min_length,
+            pw_history_count,
             credential_policy,
             webauthn_att_ca_list,
             limit_search_max_filter_test,
             limit_search_max_results,
             allow_primary_cred_fallback,
+            allow_unix_password,
+            allow_api_tokens,
+            allow_email_link,
+            password_breach_check,
+            pw_max_age,
+            authsession_idle_timeout,
+            login_host_tags,
+            ssh_key_allowed_types,
+            ssh_key_rsa_min_bits,
+            posix_default_shell,
+            posix_home_template,
+            posix_gecos_format,
+            posix_home_skeleton,
+            network_policy,
+            lockout_policy,
+            account_lifetime,
+            account_archive_delay,
         })
     }
 }
@@ -74,11 +294,29 @@ pub(crate) struct ResolvedAccountPolicy {
     privilege_expiry: u32,
     authsession_expiry: u32,
     pw_min_length: u32,
+    pw_history_count: u32,
     credential_policy: CredentialType,
     webauthn_att_ca_list: Option<AttestationCaList>,
     limit_search_max_filter_test: Option<u64>,
     limit_search_max_results: Option<u64>,
     allow_primary_cred_fallback: Option<bool>,
+    allow_unix_password: Option<bool>,
+    allow_api_tokens: Option<bool>,
+    allow_email_link: Option<bool>,
+    password_breach_check: Option<bool>,
+    pw_max_age: Option<u32>,
+    authsession_idle_timeout: Option<u32>,
+    login_host_tags: Option<BTreeSet<String>>,
+    ssh_key_allowed_types: Option<BTreeSet<String>>,
+    ssh_key_rsa_min_bits: u32,
+    posix_default_shell: Option<String>,
+    posix_home_template: Option<String>,
+    posix_gecos_format: Option<String>,
+    posix_home_skeleton: Option<String>,
+    network_policies: Vec<NetworkPolicy>,
+    lockout_policy: Option<LockoutPolicy>,
+    account_lifetime: Option<u32>,
+    account_archive_delay: Option<u32>,
 }
 
 impl ResolvedAccountPolicy {
@@ -88,11 +326,46 @@ impl ResolvedAccountPolicy {
             privilege_expiry: DEFAULT_AUTH_PRIVILEGE_EXPIRY,
             authsession_expiry: DEFAULT_AUTH_SESSION_EXPIRY,
             pw_min_length: PW_MIN_LENGTH,
+            pw_history_count: 0,
             credential_policy: CredentialType::Any,
             webauthn_att_ca_list: None,
             limit_search_max_filter_test: Some(DEFAULT_LIMIT_SEARCH_MAX_FILTER_TEST),
             limit_search_max_results: Some(DEFAULT_LIMIT_SEARCH_MAX_RESULTS),
             allow_primary_cred_fallback: None,
+            allow_unix_password: None,
+            allow_api_tokens: None,
+            allow_email_link: None,
+            password_breach_check: None,
+            pw_max_age: None,
+            authsession_idle_timeout: None,
+            login_host_tags: None,
+            ssh_key_allowed_types: None,
+            ssh_key_rsa_min_bits: 0,
+            posix_default_shell: None,
+            posix_home_template: None,
+            posix_gecos_format: None,
+            posix_home_skeleton: None,
+            network_policies: Vec::with_capacity(0),
+            lockout_policy: None,
+            account_lifetime: None,
+            account_archive_delay: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn test_network_policy(
+        trusted_networks: &[&str],
+        credential_policy: CredentialType,
+    ) -> Self {
+        ResolvedAccountPolicy {
+            network_policies: vec![NetworkPolicy {
+                trusted_networks: trusted_networks
+                    .iter()
+                    .map(|net| TrustedNetwork::from_str(net).unwrap())
+                    .collect(),
+                credential_policy,
+            }],
+            ..Self::test_policy()
         }
     }
 
@@ -105,11 +378,29 @@ impl ResolvedAccountPolicy {
             privilege_expiry: MAXIMUM_AUTH_PRIVILEGE_EXPIRY,
             authsession_expiry: MAXIMUM_AUTH_SESSION_EXPIRY,
             pw_min_length: PW_MIN_LENGTH,
+            pw_history_count: 0,
             credential_policy: CredentialType::Any,
             webauthn_att_ca_list: None,
             limit_search_max_filter_test: None,
             limit_search_max_results: None,
             allow_primary_cred_fallback: None,
+            allow_unix_password: None,
+            allow_api_tokens: None,
+            allow_email_link: None,
+            password_breach_check: None,
+            pw_max_age: None,
+            authsession_idle_timeout: None,
+            login_host_tags: None,
+            ssh_key_allowed_types: None,
+            ssh_key_rsa_min_bits: 0,
+            posix_default_shell: None,
+            posix_home_template: None,
+            posix_gecos_format: None,
+            posix_home_skeleton: None,
+            network_policies: Vec::with_capacity(0),
+            lockout_policy: None,
+            account_lifetime: None,
+            account_archive_delay: None,
         };
 
         iter.for_each(|acc_pol| {
@@ -128,6 +419,11 @@ impl ResolvedAccountPolicy {
                 accumulate.pw_min_length = acc_pol.pw_min_length
             }
 
+            // Take the longer password history
+            if acc_pol.pw_history_count > accumulate.pw_history_count {
+                accumulate.pw_history_count = acc_pol.pw_history_count
+            }
+
             // Take the greater credential type policy
             if acc_pol.credential_policy > accumulate.credential_policy {
                 accumulate.credential_policy = acc_pol.credential_policy
@@ -168,6 +464,127 @@ impl ResolvedAccountPolicy {
                         None => Some(allow_primary_cred_fallback),
                     };
             }
+
+            // Unix passwords are denied if any policy denies them.
+            if let Some(allow_unix_password) = acc_pol.allow_unix_password {
+                accumulate.allow_unix_password =
+                    Some(allow_unix_password && accumulate.allow_unix_password.unwrap_or(true));
+            }
+
+            // Api tokens are denied if any policy denies them.
+            if let Some(allow_api_tokens) = acc_pol.allow_api_tokens {
+                accumulate.allow_api_tokens =
+                    Some(allow_api_tokens && accumulate.allow_api_tokens.unwrap_or(true));
+            }
+
+            // Email links are denied if any policy denies them.
+            if let Some(allow_email_link) = acc_pol.allow_email_link {
+                accumulate.allow_email_link =
+                    Some(allow_email_link && accumulate.allow_email_link.unwrap_or(true));
+            }
+
+            // Breached passwords are checked for if any policy requires it.
+            if let Some(password_breach_check) = acc_pol.password_breach_check {
+                accumulate.password_breach_check = Some(
+                    password_breach_check || accumulate.password_breach_check.unwrap_or(false),
+                );
+            }
+
+            // Take the shorter password age.
+            if let Some(pol_max_age) = acc_pol.pw_max_age {
+                accumulate.pw_max_age = Some(
+                    accumulate
+                        .pw_max_age
+                        .map_or(pol_max_age, |acc_max_age| acc_max_age.min(pol_max_age)),
+                );
+            }
+
+            // Take the shorter idle timeout.
+            if let Some(pol_idle_timeout) = acc_pol.authsession_idle_timeout {
+                accumulate.authsession_idle_timeout = Some(
+                    accumulate
+                        .authsession_idle_timeout
+                        .map_or(pol_idle_timeout, |acc_idle_timeout| {
+                            acc_idle_timeout.min(pol_idle_timeout)
+                        }),
+                );
+            }
+
+            // Each policy grants access to the hosts with its tags, so the tags of every
+            // policy are combined.
+            if let Some(pol_tags) = acc_pol.login_host_tags {
+                accumulate
+                    .login_host_tags
+                    .get_or_insert_with(BTreeSet::default)
+                    .extend(pol_tags);
+            }
+
+            // Only key types that every policy allows are permitted.
+            if let Some(pol_types) = acc_pol.ssh_key_allowed_types {
+                accumulate.ssh_key_allowed_types = Some(match accumulate.ssh_key_allowed_types {
+                    Some(acc_types) => acc_types.intersection(&pol_types).cloned().collect(),
+                    None => pol_types,
+                });
+            }
+
+            // Take the larger rsa key length
+            if acc_pol.ssh_key_rsa_min_bits > accumulate.ssh_key_rsa_min_bits {
+                accumulate.ssh_key_rsa_min_bits = acc_pol.ssh_key_rsa_min_bits
+            }
+
+            // Posix defaults can't be combined, so the first policy to define each is used.
+            // Policies are ordered so that those of groups the account is directly a
+            // member of come first.
+            if accumulate.posix_default_shell.is_none() {
+                accumulate.posix_default_shell = acc_pol.posix_default_shell;
+            }
+
+            if accumulate.posix_home_template.is_none() {
+                accumulate.posix_home_template = acc_pol.posix_home_template;
+            }
+
+            if accumulate.posix_gecos_format.is_none() {
+                accumulate.posix_gecos_format = acc_pol.posix_gecos_format;
+            }
+
+            if accumulate.posix_home_skeleton.is_none() {
+                accumulate.posix_home_skeleton = acc_pol.posix_home_skeleton;
+            }
+
+            // Each network policy is kept, as the networks of one policy can't be
+            // combined with the credential type of another.
+            if let Some(network_policy) = acc_pol.network_policy {
+                accumulate.network_policies.push(network_policy);
+            }
+
+            // Take the lowest threshold, and the longest window and duration.
+            if let Some(pol_lockout) = acc_pol.lockout_policy {
+                accumulate.lockout_policy = Some(match accumulate.lockout_policy {
+                    Some(acc_lockout) => LockoutPolicy {
+                        threshold: pol_lockout.threshold.min(acc_lockout.threshold),
+                        window: pol_lockout.window.max(acc_lockout.window),
+                        duration: pol_lockout.duration.max(acc_lockout.duration),
+                    },
+                    None => pol_lockout,
+                });
+            }
+
+            // Take the shorter lifetime and archive delay.
+            if let Some(pol_lifetime) = acc_pol.account_lifetime {
+                accumulate.account_lifetime = Some(
+                    accumulate
+                        .account_lifetime
+                        .map_or(pol_lifetime, |acc_lifetime| acc_lifetime.min(pol_lifetime)),
+                );
+            }
+
+            if let Some(pol_delay) = acc_pol.account_archive_delay {
+                accumulate.account_archive_delay = Some(
+                    accumulate
+                        .account_archive_delay
+                        .map_or(pol_delay, |acc_delay| acc_delay.min(pol_delay)),
+                );
+            }
         });
 
         accumulate
@@ -185,6 +602,12 @@ impl ResolvedAccountPolicy {
         self.pw_min_length
     }
 
+    /// The number of previous passwords that are remembered and may not be reused. If
+    /// zero, password history is not kept.
+    pub(crate) fn pw_history_count(&self) -> u32 {
+        self.pw_history_count
+    }
+
     pub(crate) fn credential_policy(&self) -> CredentialType {
         self.credential_policy
     }
@@ -204,12 +627,197 @@ impl ResolvedAccountPolicy {
     pub(crate) fn allow_primary_cred_fallback(&self) -> Option<bool> {
         self.allow_primary_cred_fallback
     }
+
+    /// If accounts may set a unix password. Unix passwords are allowed unless a policy
+    /// denies them.
+    pub(crate) fn allow_unix_password(&self) -> bool {
+        self.allow_unix_password.unwrap_or(true)
+    }
+
+    /// If persons may issue api tokens to themselves. This must be allowed by a policy, and
+    /// no policy may deny it.
+    pub(crate) fn allow_api_tokens(&self) -> bool {
+        self.allow_api_tokens.unwrap_or(false)
+    }
+
+    /// If the account may authenticate with a link that is sent to their mail address. This
+    /// must be allowed by a policy, and no policy may deny it.
+    pub(crate) fn allow_email_link(&self) -> bool {
+        self.allow_email_link.unwrap_or(false)
+    }
+
+    /// If new passwords must be checked against the dataset of breached passwords, when the
+    /// server has one. Any policy may require it.
+    pub(crate) fn password_breach_check(&self) -> bool {
+        self.password_breach_check.unwrap_or(false)
+    }
+
+    /// How long a password may be used for before it must be changed. If `None`, passwords
+    /// don't expire.
+    pub(crate) fn pw_max_age(&self) -> Option<Duration> {
+        self.pw_max_age
+            .map(|max_age| Duration::from_secs(max_age as u64))
+    }
+
+    /// How long a session may go unused before it expires, in seconds. If `None`, sessions
+    /// only expire at their absolute expiry.
+    pub(crate) fn authsession_idle_timeout(&self) -> Option<u32> {
+        self.authsession_idle_timeout
+            .map(|idle_timeout| idle_timeout.max(MINIMUM_AUTH_SESSION_IDLE_TIMEOUT))
+    }
+
+    /// The host tags of the machines that the account may log in to. If `None`, logins
+    /// are not restricted to any hosts.
+    pub(crate) fn login_host_tags(&self) -> Option<&BTreeSet<String>> {
+        self.login_host_tags.as_ref()
+    }
+
+    /// If an ssh public key may be added to an account. The key type must be in the allowed
+    /// types if any are defined, and rsa keys must meet the minimum length.
+    pub(crate) fn ssh_key_permitted(&self, key: &SshPublicKey) -> bool {
+        let type_allowed = self
+            .ssh_key_allowed_types
+            .as_ref()
+            .map_or(true, |types| types.contains(key.key_type.name));
+
+        let length_allowed = match key.kind {
+            PublicKeyKind::Rsa(_) => key.bits() >= self.ssh_key_rsa_min_bits as usize,
+            _ => true,
+        };
+
+        type_allowed && length_allowed
+    }
+
+    /// The login shell of accounts that have not set their own.
+    pub(crate) fn posix_default_shell(&self) -> Option<&str> {
+        self.posix_default_shell.as_deref()
+    }
+
+    pub(crate) fn posix_home_template(&self) -> Option<&str> {
+        self.posix_home_template.as_deref()
+    }
+
+    pub(crate) fn posix_gecos_format(&self) -> Option<&str> {
+        self.posix_gecos_format.as_deref()
+    }
+
+    /// The name of the skeleton that unixd copies into new home directories.
+    pub(crate) fn posix_home_skeleton(&self) -> Option<&str> {
+        self.posix_home_skeleton.as_deref()
+    }
+
+    /// How long after creation an account without an expiry is set to expire.
+    pub(crate) fn account_lifetime(&self) -> Option<Duration> {
+        self.account_lifetime
+            .map(|lifetime| Duration::from_secs(lifetime as u64))
+    }
+
+    /// How long after expiry an account is moved to the recycle bin.
+    pub(crate) fn account_archive_delay(&self) -> Option<Duration> {
+        self.account_archive_delay
+            .map(|delay| Duration::from_secs(delay as u64))
+    }
+
+    /// The softlock policy to apply to a credential. If the account policy defines a lockout
+    /// it replaces the default policy of the credential type.
+    pub(crate) fn softlock_policy(&self, policy: CredSoftLockPolicy) -> CredSoftLockPolicy {
+        match (&self.lockout_policy, policy) {
+            (_, CredSoftLockPolicy::Unrestricted) => CredSoftLockPolicy::Unrestricted,
+            (Some(lockout), _) => CredSoftLockPolicy::Lockout {
+                threshold: lockout.threshold,
+                window: Duration::from_secs(lockout.window as u64),
+                duration: Duration::from_secs(lockout.duration as u64),
+            },
+            (None, policy) => policy,
+        }
+    }
+
+    /// The minimum credential type required to authenticate from this source. Each
+    /// network policy that does not trust the source contributes its requirement, and
+    /// the strictest is taken.
+    pub(crate) fn network_credential_policy(&self, source: &Source) -> CredentialType {
+        let ip = match source {
+            Source::Internal => return CredentialType::Any,
+            Source::Https(ip) | Source::Ldaps(ip) => ip,
+        };
+
+        self.network_policies
+            .iter()
+            .filter(|net_pol| !net_pol.is_trusted(ip))
+            .map(|net_pol| net_pol.credential_policy)
+            .max()
+            .unwrap_or(CredentialType::Any)
+    }
+
+    /// The policy as it is presented to clients, so that they can show the requirements that
+    /// apply to an account.
+    pub(crate) fn to_effective_policy(&self) -> EffectiveAccountPolicy {
+        EffectiveAccountPolicy {
+            credential_type_minimum: self.credential_policy.to_string(),
+            password_minimum_length: self.pw_min_length,
+            password_history_count: self.pw_history_count,
+            webauthn_attestation_required: self.webauthn_att_ca_list.is_some(),
+            allow_primary_credential_fallback: self.allow_primary_cred_fallback,
+            allow_unix_password: self.allow_unix_password(),
+            allow_api_tokens: self.allow_api_tokens(),
+            allow_email_link: self.allow_email_link(),
+            password_breach_check: self.password_breach_check(),
+            password_maximum_age: self.pw_max_age,
+            authsession_expiry: self.authsession_expiry,
+            authsession_idle_timeout: self.authsession_idle_timeout(),
+            privilege_expiry: self.privilege_expiry,
+            limit_search_max_results: self.limit_search_max_results,
+            limit_search_max_filter_test: self.limit_search_max_filter_test,
+            login_host_tags: self
+                .login_host_tags
+                .as_ref()
+                .map(|tags| tags.iter().cloned().collect()),
+            ssh_key_allowed_types: self
+                .ssh_key_allowed_types
+                .as_ref()
+                .map(|types| types.iter().cloned().collect()),
+            ssh_key_rsa_minimum_bits: self.ssh_key_rsa_min_bits,
+            posix_default_shell: self.posix_default_shell.clone(),
+            posix_home_template: self.posix_home_template.clone(),
+            posix_gecos_format: self.posix_gecos_format.clone(),
+            posix_home_skeleton: self.posix_home_skeleton.clone(),
+            network_policies: self
+                .network_policies
+                .iter()
+                .map(|net_pol| AccountPolicyNetwork {
+                    trusted_networks: net_pol
+                        .trusted_networks
+                        .iter()
+                        .map(|net| net.to_string())
+                        .collect(),
+                    credential_type_minimum: net_pol.credential_policy.to_string(),
+                })
+                .collect(),
+            lockout: self
+                .lockout_policy
+                .as_ref()
+                .map(|lockout| AccountPolicyLockout {
+                    threshold: lockout.threshold,
+                    window: lockout.window,
+                    duration: lockout.duration,
+                }),
+            account_lifetime: self.account_lifetime.map(u64::from),
+            account_archive_delay: self.account_archive_delay.map(u64::from),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{AccountPolicy, CredentialType, ResolvedAccountPolicy};
+    use super::{
+        AccountPolicy, CredSoftLockPolicy, CredentialType, LockoutPolicy, NetworkPolicy,
+        ResolvedAccountPolicy, TrustedNetwork,
+    };
     use crate::prelude::*;
+    use sshkey_attest::proto::PublicKey as SshPublicKey;
+    use std::collections::BTreeSet;
+    use std::net::IpAddr;
+    use std::str::FromStr;
     use webauthn_rs_core::proto::AttestationCaListBuilder;
 
     #[test]
@@ -280,11 +888,32 @@ jAGGiQIwHFj+dJZYUJR786osByBelJYsVZd2GbHQu209b5RCmGQ21gpSAk9QZW4B
             privilege_expiry: 100,
             authsession_expiry: 100,
             pw_min_length: 11,
+            pw_history_count: 5,
             credential_policy: CredentialType::Mfa,
             webauthn_att_ca_list: Some(att_ca_list_a),
             limit_search_max_filter_test: Some(10),
             limit_search_max_results: Some(10),
             allow_primary_cred_fallback: None,
+            allow_unix_password: None,
+            allow_api_tokens: Some(true),
+            allow_email_link: Some(true),
+            password_breach_check: Some(true),
+            pw_max_age: Some(86400 * 90),
+            authsession_idle_timeout: Some(1800),
+            login_host_tags: Some(BTreeSet::from(["prod".to_string()])),
+            ssh_key_allowed_types: Some(BTreeSet::from([
+                "ssh-ed25519".to_string(),
+                "ssh-rsa".to_string(),
+            ])),
+            ssh_key_rsa_min_bits: 2048,
+            posix_default_shell: None,
+            posix_home_template: Some("{name}".to_string()),
+            posix_gecos_format: None,
+            posix_home_skeleton: None,
+            network_policy: None,
+            lockout_policy: None,
+            account_lifetime: Some(86400 * 90),
+            account_archive_delay: None,
         };
 
         let mut att_ca_builder = AttestationCaListBuilder::new();
@@ -302,11 +931,32 @@ jAGGiQIwHFj+dJZYUJR786osByBelJYsVZd2GbHQu209b5RCmGQ21gpSAk9QZW4B
             privilege_expiry: 150,
             authsession_expiry: 50,
             pw_min_length: 15,
+            pw_history_count: 0,
             credential_policy: CredentialType::Passkey,
             webauthn_att_ca_list: Some(att_ca_list_b),
             limit_search_max_filter_test: Some(5),
             limit_search_max_results: Some(15),
             allow_primary_cred_fallback: Some(false),
+            allow_unix_password: Some(false),
+            allow_api_tokens: None,
+            allow_email_link: None,
+            password_breach_check: None,
+            pw_max_age: Some(86400 * 180),
+            authsession_idle_timeout: Some(60),
+            login_host_tags: Some(BTreeSet::from(["dev".to_string()])),
+            ssh_key_allowed_types: Some(BTreeSet::from([
+                "ssh-ed25519".to_string(),
+                "ecdsa-sha2-nistp256".to_string(),
+            ])),
+            ssh_key_rsa_min_bits: 3072,
+            posix_default_shell: Some("/bin/zsh".to_string()),
+            posix_home_template: Some("{uuid}".to_string()),
+            posix_gecos_format: None,
+            posix_home_skeleton: None,
+            network_policy: None,
+            lockout_policy: None,
+            account_lifetime: Some(86400 * 30),
+            account_archive_delay: Some(86400 * 7),
         };
 
         let rap = ResolvedAccountPolicy::fold_from([policy_a, policy_b].into_iter());
@@ -314,10 +964,44 @@ jAGGiQIwHFj+dJZYUJR786osByBelJYsVZd2GbHQu209b5RCmGQ21gpSAk9QZW4B
         assert_eq!(rap.privilege_expiry(), 100);
         assert_eq!(rap.authsession_expiry(), 50);
         assert_eq!(rap.pw_min_length(), 15);
+        assert_eq!(rap.pw_history_count(), 5);
         assert_eq!(rap.credential_policy, CredentialType::Passkey);
         assert_eq!(rap.limit_search_max_results(), Some(15));
         assert_eq!(rap.limit_search_max_filter_test(), Some(10));
         assert_eq!(rap.allow_primary_cred_fallback(), Some(false));
+        assert!(!rap.allow_unix_password());
+        assert!(rap.allow_api_tokens());
+        assert!(rap.allow_email_link());
+        assert!(rap.password_breach_check());
+        assert_eq!(rap.pw_max_age(), Some(Duration::from_secs(86400 * 90)));
+        // The shorter idle timeout applies, but never less than the minimum.
+        assert_eq!(
+            rap.authsession_idle_timeout(),
+            Some(MINIMUM_AUTH_SESSION_IDLE_TIMEOUT)
+        );
+        // The hosts of every policy may be logged in to.
+        assert_eq!(
+            rap.login_host_tags(),
+            Some(&BTreeSet::from(["dev".to_string(), "prod".to_string()]))
+        );
+        assert_eq!(
+            rap.ssh_key_allowed_types,
+            Some(BTreeSet::from(["ssh-ed25519".to_string()]))
+        );
+        assert_eq!(rap.ssh_key_rsa_min_bits, 3072);
+        // The first policy to define a posix default is used.
+        assert_eq!(rap.posix_default_shell(), Some("/bin/zsh"));
+        assert_eq!(rap.posix_home_template(), Some("{name}"));
+        assert_eq!(rap.posix_gecos_format(), None);
+        // The shorter lifetime is taken.
+        assert_eq!(
+            rap.account_lifetime(),
+            Some(Duration::from_secs(86400 * 30))
+        );
+        assert_eq!(
+            rap.account_archive_delay(),
+            Some(Duration::from_secs(86400 * 7))
+        );
 
         let mut att_ca_builder = AttestationCaListBuilder::new();
 
@@ -329,4 +1013,185 @@ jAGGiQIwHFj+dJZYUJR786osByBelJYsVZd2GbHQu209b5RCmGQ21gpSAk9QZW4B
 
         assert_eq!(rap.webauthn_att_ca_list, Some(att_ca_list_ex));
     }
+
+    #[test]
+    fn test_idm_account_policy_trusted_network() {
+        let ip = |s: &str| IpAddr::from_str(s).unwrap();
+
+        let net = TrustedNetwork::from_str("10.0.0.0/8").unwrap();
+        assert!(net.contains(&ip("10.1.2.3")));
+        assert!(net.contains(&ip("::ffff:10.1.2.3")));
+        assert!(!net.contains(&ip("11.0.0.1")));
+        assert!(!net.contains(&ip("2001:db8::1")));
+
+        let net = TrustedNetwork::from_str("2001:db8::/32").unwrap();
+        assert!(net.contains(&ip("2001:db8:1::1")));
+        assert!(!net.contains(&ip("2001:db9::1")));
+
+        // A single host
+        let net = TrustedNetwork::from_str("192.168.0.1").unwrap();
+        assert!(net.contains(&ip("192.168.0.1")));
+        assert!(!net.contains(&ip("192.168.0.2")));
+
+        // Everything
+        let net = TrustedNetwork::from_str("0.0.0.0/0").unwrap();
+        assert!(net.contains(&ip("192.168.0.2")));
+
+        assert!(TrustedNetwork::from_str("10.0.0.0/33").is_err());
+        assert!(TrustedNetwork::from_str("10.0.0/8").is_err());
+        assert!(TrustedNetwork::from_str("10.0.0.0/").is_err());
+        assert!(TrustedNetwork::from_str("").is_err());
+    }
+
+    #[test]
+    fn test_idm_account_policy_resolve_network() {
+        let policy_a = AccountPolicy {
+            network_policy: Some(NetworkPolicy {
+                trusted_networks: vec![TrustedNetwork::from_str("10.0.0.0/8").unwrap()],
+                credential_policy: CredentialType::Mfa,
+            }),
+            ..Default::default()
+        };
+
+        let policy_b = AccountPolicy {
+            network_policy: Some(NetworkPolicy {
+                trusted_networks: vec![
+                    TrustedNetwork::from_str("10.0.0.0/16").unwrap(),
+                    TrustedNetwork::from_str("192.168.0.0/24").unwrap(),
+                ],
+                credential_policy: CredentialType::Passkey,
+            }),
+            ..Default::default()
+        };
+
+        let rap = ResolvedAccountPolicy::fold_from([policy_a, policy_b].into_iter());
+
+        let source = |s: &str| Source::Https(IpAddr::from_str(s).unwrap());
+
+        // Trusted by both policies.
+        assert_eq!(
+            rap.network_credential_policy(&source("10.0.1.1")),
+            CredentialType::Any
+        );
+        // Only trusted by the first policy.
+        assert_eq!(
+            rap.network_credential_policy(&source("10.1.1.1")),
+            CredentialType::Passkey
+        );
+        // Only trusted by the second policy.
+        assert_eq!(
+            rap.network_credential_policy(&source("192.168.0.1")),
+            CredentialType::Mfa
+        );
+        // Trusted by neither, the strictest applies.
+        assert_eq!(
+            rap.network_credential_policy(&source("203.0.113.1")),
+            CredentialType::Passkey
+        );
+        // Internal operations are never restricted.
+        assert_eq!(
+            rap.network_credential_policy(&Source::Internal),
+            CredentialType::Any
+        );
+
+        // Without any trusted networks, the source is never trusted.
+        let policy_c = AccountPolicy {
+            network_policy: Some(NetworkPolicy {
+                trusted_networks: Vec::with_capacity(0),
+                credential_policy: CredentialType::Invalid,
+            }),
+            ..Default::default()
+        };
+
+        let rap = ResolvedAccountPolicy::fold_from([policy_c].into_iter());
+
+        assert_eq!(
+            rap.network_credential_policy(&source("10.0.1.1")),
+            CredentialType::Invalid
+        );
+    }
+
+    #[test]
+    fn test_idm_account_policy_resolve_lockout() {
+        // Without a lockout, the credential keeps its own policy.
+        let rap = ResolvedAccountPolicy::fold_from([AccountPolicy::default()].into_iter());
+        assert_eq!(
+            rap.softlock_policy(CredSoftLockPolicy::Password),
+            CredSoftLockPolicy::Password
+        );
+
+        let policy_a = AccountPolicy {
+            lockout_policy: Some(LockoutPolicy {
+                threshold: 5,
+                window: 600,
+                duration: 900,
+            }),
+            ..Default::default()
+        };
+
+        let policy_b = AccountPolicy {
+            lockout_policy: Some(LockoutPolicy {
+                threshold: 10,
+                window: 3600,
+                duration: 300,
+            }),
+            ..Default::default()
+        };
+
+        let rap = ResolvedAccountPolicy::fold_from([policy_a, policy_b].into_iter());
+
+        assert_eq!(
+            rap.softlock_policy(CredSoftLockPolicy::Password),
+            CredSoftLockPolicy::Lockout {
+                threshold: 5,
+                window: Duration::from_secs(3600),
+                duration: Duration::from_secs(900),
+            }
+        );
+
+        // Credentials that are never locked are unaffected.
+        assert_eq!(
+            rap.softlock_policy(CredSoftLockPolicy::Unrestricted),
+            CredSoftLockPolicy::Unrestricted
+        );
+    }
+
+    #[test]
+    fn test_idm_account_policy_ssh_key() {
+        let ed25519 = SshPublicKey::from_string(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAeGW1P6Pc2rPq0XqbRaDKBcXZUPRklo0L1EyR30CwoP",
+        )
+        .unwrap();
+        let rsa = SshPublicKey::from_string(concat!(
+            "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQDTcXpclurQpyOHZBM/cDY9EvInSYkYSGe51by/wJP0Njgi",
+            "GZUJ3HTaPqoGWux0PKd7KJki+onLYt4IwDV1RhV/GtMML2U9v94+pA8RIK4khCxvpUxlM7Kt/svjOzzzqiZfKdV37/",
+            "OUXmM7bwVGOvm3EerDOwmO/QdzNGfkca12aWLoz97YrleXnCoAzr3IN7j3rwmfJGDyuUtGTdmyS/QWhK9FPr8Ic3eM",
+            "QK1JSAQqVfGhA8lLbJHmnQ/b/KMl2lzzp7SXej0wPUfvI/IP3NGb8irLzq8+JssAzXGJ+HMql+mNHiSuPaktbFzZ6y",
+            "ikMR6Rx/psU07nAkxKZDEYpNVv"
+        ))
+        .unwrap();
+
+        // Without a policy, any key is permitted.
+        let rap = ResolvedAccountPolicy::fold_from([AccountPolicy::default()].into_iter());
+        assert!(rap.ssh_key_permitted(&ed25519));
+        assert!(rap.ssh_key_permitted(&rsa));
+
+        // The rsa key is 2048 bits.
+        let policy = AccountPolicy {
+            ssh_key_rsa_min_bits: 3072,
+            ..Default::default()
+        };
+        let rap = ResolvedAccountPolicy::fold_from([policy].into_iter());
+        assert!(rap.ssh_key_permitted(&ed25519));
+        assert!(!rap.ssh_key_permitted(&rsa));
+
+        let policy = AccountPolicy {
+            ssh_key_allowed_types: Some(BTreeSet::from(["ssh-rsa".to_string()])),
+            ssh_key_rsa_min_bits: 2048,
+            ..Default::default()
+        };
+        let rap = ResolvedAccountPolicy::fold_from([policy].into_iter());
+        assert!(!rap.ssh_key_permitted(&ed25519));
+        assert!(rap.ssh_key_permitted(&rsa));
+    }
 }
@@ -0,0 +1,557 @@
+//! Self service account recovery. A person who has lost their credentials can request a
+//! one time code that is sent to their primary mail address. Presenting this code allows
+//! them to begin a credential update session for their account.
+//!
+//! Pending recoveries are held in memory on the server that issued the code, and are not
+//! replicated.
+
+use std::time::Duration;
+
+use rand::prelude::*;
+use time::OffsetDateTime;
+
+use crate::idm::account::Account;
+use crate::idm::audit::{AccountRecoveryDeniedReason, AuditEvent};
+use crate::idm::credupdatesession::{
+    CredentialUpdateSessionStatus, CredentialUpdateSessionToken, InitCredentialUpdateEvent,
+};
+use crate::idm::server::IdmServerProxyWriteTransaction;
+use crate::prelude::*;
+use crate::server::identity::Source;
+use crate::utils::uuid_from_duration;
+
+/// How long a recovery code is valid for after it is issued.
+pub const ACCOUNT_RECOVERY_CODE_TTL: Duration = Duration::from_secs(15 * 60);
+/// The minimum time between recovery codes being issued to the same account.
+pub const ACCOUNT_RECOVERY_REQUEST_INTERVAL: Duration = Duration::from_secs(2 * 60);
+/// How many incorrect codes may be presented before the recovery is abandoned.
+pub const ACCOUNT_RECOVERY_MAX_ATTEMPTS: u8 = 5;
+
+#[derive(Debug, Clone)]
+pub(crate) struct AccountRecoveryState {
+    target: Uuid,
+    code: String,
+    issued_at: Duration,
+    attempts: u8,
+}
+
+#[derive(Debug)]
+pub struct AccountRecoveryBeginEvent {
+    pub source: Source,
+    pub name: String,
+}
+
+impl AccountRecoveryBeginEvent {
+    pub fn new(source: Source, name: &str) -> Self {
+        AccountRecoveryBeginEvent {
+            source,
+            name: name.to_string(),
+        }
+    }
+}
+
+pub struct AccountRecoveryVerifyEvent {
+    pub source: Source,
+    pub recovery_id: Uuid,
+    pub code: String,
+}
+
+impl AccountRecoveryVerifyEvent {
+    pub fn new(source: Source, recovery_id: Uuid, code: &str) -> Self {
+        AccountRecoveryVerifyEvent {
+            source,
+            recovery_id,
+            code: code.trim().to_string(),
+        }
+    }
+}
+
+/// The content of the message that must be sent to the person recovering their account.
+#[derive(Debug)]
+pub struct AccountRecoveryMessage {
+    pub mail: String,
+    pub displayname: String,
+    pub spn: String,
+    pub code: String,
+    pub expiry: OffsetDateTime,
+}
+
+#[derive(Debug)]
+pub struct AccountRecoveryBegin {
+    /// The identifier of this recovery, which must be presented with the code.
+    pub recovery_id: Uuid,
+    /// The message to send. This is `None` if no code was issued, which the caller must
+    /// not reveal so that the existence of accounts is not disclosed.
+    pub message: Option<AccountRecoveryMessage>,
+}
+
+pub enum AccountRecoveryVerify {
+    Success(CredentialUpdateSessionToken, CredentialUpdateSessionStatus),
+    InvalidCode,
+    Expired,
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    /// Begin recovery of an account. The result of this must be committed even when no
+    /// message is issued, as this records the rate limiting state.
+    #[instrument(level = "debug", skip_all)]
+    pub fn account_recovery_begin(
+        &mut self,
+        ev: &AccountRecoveryBeginEvent,
+        ct: Duration,
+    ) -> Result<AccountRecoveryBegin, OperationError> {
+        self.expire_account_recoveries(ct);
+
+        let expiry = ct + ACCOUNT_RECOVERY_CODE_TTL;
+        let recovery_id = uuid_from_duration(expiry, self.sid);
+
+        let Some(account) = self.account_recovery_target(&ev.name, ct)? else {
+            return Ok(AccountRecoveryBegin {
+                recovery_id,
+                message: None,
+            });
+        };
+
+        let Some(mail) = account.mail_primary.clone() else {
+            security_info!(%account.uuid, "Account recovery requested for account without a mail address");
+            return Ok(AccountRecoveryBegin {
+                recovery_id,
+                message: None,
+            });
+        };
+
+        // If a code was issued recently, continue with that recovery rather than sending
+        // another message.
+        let recent = self
+            .account_recovery
+            .iter()
+            .find(|(_, state)| {
+                state.target == account.uuid
+                    && ct < state.issued_at + ACCOUNT_RECOVERY_REQUEST_INTERVAL
+            })
+            .map(|(id, _)| *id);
+
+        if let Some(recovery_id) = recent {
+            security_info!(%account.uuid, "Account recovery requested too frequently");
+            self.submit_account_recovery_denied(
+                &ev.source,
+                &account,
+                AccountRecoveryDeniedReason::RateLimited,
+                ct,
+            );
+            return Ok(AccountRecoveryBegin {
+                recovery_id,
+                message: None,
+            });
+        }
+
+        // Only the latest code for an account is valid.
+        let superseded: Vec<Uuid> = self
+            .account_recovery
+            .iter()
+            .filter(|(_, state)| state.target == account.uuid)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in superseded {
+            self.account_recovery.remove(&id);
+        }
+
+        let code = format!("{:08}", thread_rng().gen_range(0..100_000_000u32));
+
+        self.account_recovery.insert(
+            recovery_id,
+            AccountRecoveryState {
+                target: account.uuid,
+                code: code.clone(),
+                issued_at: ct,
+                attempts: 0,
+            },
+        );
+
+        security_info!(%account.uuid, "Issued account recovery code");
+
+        self.submit_audit_event(AuditEvent::AccountRecoveryRequested {
+            source: ev.source.clone().into(),
+            uuid: account.uuid,
+            spn: account.spn.clone(),
+            time: OffsetDateTime::UNIX_EPOCH + ct,
+        });
+
+        Ok(AccountRecoveryBegin {
+            recovery_id,
+            message: Some(AccountRecoveryMessage {
+                mail,
+                displayname: account.displayname,
+                spn: account.spn,
+                code,
+                expiry: OffsetDateTime::UNIX_EPOCH + expiry,
+            }),
+        })
+    }
+
+    /// Check a recovery code, and if it is correct begin a credential update session for the
+    /// account. The result of this must be committed even when the code is incorrect, as this
+    /// records the failed attempt.
+    #[instrument(level = "debug", skip_all)]
+    pub fn account_recovery_verify(
+        &mut self,
+        ev: &AccountRecoveryVerifyEvent,
+        ct: Duration,
+    ) -> Result<AccountRecoveryVerify, OperationError> {
+        self.expire_account_recoveries(ct);
+
+        let Some(mut state) = self.account_recovery.get(&ev.recovery_id).cloned() else {
+            security_info!("Account recovery does not exist or has expired");
+            return Ok(AccountRecoveryVerify::Expired);
+        };
+
+        let entry = self.qs_write.internal_search_uuid(state.target)?;
+        let account = Account::try_from_entry_rw(entry.as_ref(), &mut self.qs_write)?;
+
+        if state.code != ev.code {
+            state.attempts += 1;
+
+            if state.attempts >= ACCOUNT_RECOVERY_MAX_ATTEMPTS {
+                security_info!(%account.uuid, "Account recovery abandoned after too many incorrect codes");
+                self.account_recovery.remove(&ev.recovery_id);
+                self.submit_account_recovery_denied(
+                    &ev.source,
+                    &account,
+                    AccountRecoveryDeniedReason::AttemptsExceeded,
+                    ct,
+                );
+                return Ok(AccountRecoveryVerify::Expired);
+            }
+
+            security_info!(%account.uuid, "Incorrect account recovery code");
+            self.account_recovery.insert(ev.recovery_id, state);
+            self.submit_account_recovery_denied(
+                &ev.source,
+                &account,
+                AccountRecoveryDeniedReason::InvalidCode,
+                ct,
+            );
+            return Ok(AccountRecoveryVerify::InvalidCode);
+        }
+
+        // The code can only be used once.
+        self.account_recovery.remove(&ev.recovery_id);
+
+        // The account may have changed since the code was issued.
+        if !account.is_within_valid_time(ct) {
+            security_info!(%account.uuid, "Account recovery denied - account is not valid");
+            return Ok(AccountRecoveryVerify::Expired);
+        }
+
+        // The person has proven they control the mail address of the account, so they may
+        // update the credentials they would be able to with a privileged session of their own.
+        let ident = Identity::from_account_recovery(entry, ev.source.clone());
+        let init_event = InitCredentialUpdateEvent::new(ident, account.uuid);
+        let (token, status) = self.init_credential_update(&init_event, ct)?;
+
+        security_info!(%account.uuid, "Account recovery code accepted");
+
+        self.submit_audit_event(AuditEvent::AccountRecoveryCompleted {
+            source: ev.source.clone().into(),
+            uuid: account.uuid,
+            spn: account.spn,
+            time: OffsetDateTime::UNIX_EPOCH + ct,
+        });
+
+        Ok(AccountRecoveryVerify::Success(token, status))
+    }
+
+    /// Resolve the account to recover. Only valid persons may recover their account.
+    fn account_recovery_target(
+        &mut self,
+        name: &str,
+        ct: Duration,
+    ) -> Result<Option<Account>, OperationError> {
+        let entry = match self
+            .qs_write
+            .name_to_uuid(name)
+            .and_then(|target| self.qs_write.internal_search_uuid(target))
+        {
+            Ok(entry) => entry,
+            Err(OperationError::NoMatchingEntries) | Err(OperationError::InvalidValueState) => {
+                security_info!("Account recovery requested for an account that does not exist");
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+        let target = entry.get_uuid();
+
+        if !entry.attribute_equality(Attribute::Class, &EntryClass::Person.to_partialvalue()) {
+            security_info!(%target, "Account recovery requested for an entry that is not a person");
+            return Ok(None);
+        }
+
+        let account = Account::try_from_entry_rw(entry.as_ref(), &mut self.qs_write)?;
+
+        if !account.is_within_valid_time(ct) {
+            security_info!(%target, "Account recovery requested for an account that is not valid");
+            return Ok(None);
+        }
+
+        Ok(Some(account))
+    }
+
+    fn expire_account_recoveries(&mut self, ct: Duration) {
+        let split_at = uuid_from_duration(ct, self.sid);
+        self.account_recovery.split_off_lt(&split_at);
+    }
+
+    fn submit_account_recovery_denied(
+        &self,
+        source: &Source,
+        account: &Account,
+        reason: AccountRecoveryDeniedReason,
+        ct: Duration,
+    ) {
+        self.submit_audit_event(AuditEvent::AccountRecoveryDenied {
+            source: source.clone().into(),
+            uuid: account.uuid,
+            spn: account.spn.clone(),
+            reason,
+            time: OffsetDateTime::UNIX_EPOCH + ct,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AccountRecoveryBeginEvent, AccountRecoveryVerify, AccountRecoveryVerifyEvent,
+        ACCOUNT_RECOVERY_MAX_ATTEMPTS, ACCOUNT_RECOVERY_REQUEST_INTERVAL,
+    };
+    use crate::idm::audit::{AccountRecoveryDeniedReason, AuditEvent};
+    use crate::prelude::*;
+    use crate::server::identity::Source;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    const TESTPERSON_UUID: Uuid = uuid!("cf231fea-1a8f-4410-a520-fd9b1a379c86");
+
+    async fn setup_test_person(idms: &IdmServer, ct: Duration, mail: bool) {
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let mut e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(TESTPERSON_UUID)),
+            (Attribute::Description, Value::new_utf8s("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("Test Person"))
+        );
+        if mail {
+            e1.add_ava(
+                Attribute::Mail,
+                Value::EmailAddress("testperson@example.com".to_string(), true),
+            );
+        }
+
+        assert!(idms_prox_write.qs_write.internal_create(vec![e1]).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    fn test_source() -> Source {
+        Source::Https("127.0.0.1".parse().expect("Invalid ip"))
+    }
+
+    #[idm_test(audit = 1)]
+    async fn test_idm_account_recovery_basic(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+        idms_audit: &mut IdmServerAudit,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        setup_test_person(idms, ct, true).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let begin = idms_prox_write
+            .account_recovery_begin(
+                &AccountRecoveryBeginEvent::new(test_source(), "testperson"),
+                ct,
+            )
+            .expect("Failed to begin account recovery");
+        assert!(idms_prox_write.commit().is_ok());
+
+        let message = begin.message.expect("No recovery message was issued");
+        assert_eq!(message.mail, "testperson@example.com");
+        assert_eq!(message.code.len(), 8);
+
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::AccountRecoveryRequested { uuid, .. }) => {
+                assert_eq!(uuid, TESTPERSON_UUID);
+            }
+            _ => panic!("Oh no"),
+        }
+
+        // An incorrect code is rejected, and recorded.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let result = idms_prox_write
+            .account_recovery_verify(
+                &AccountRecoveryVerifyEvent::new(test_source(), begin.recovery_id, "nope"),
+                ct,
+            )
+            .expect("Failed to verify account recovery");
+        assert!(matches!(result, AccountRecoveryVerify::InvalidCode));
+        assert!(idms_prox_write.commit().is_ok());
+
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::AccountRecoveryDenied { reason, .. }) => {
+                assert_eq!(reason, AccountRecoveryDeniedReason::InvalidCode);
+            }
+            _ => panic!("Oh no"),
+        }
+
+        // The correct code begins a credential update session.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let result = idms_prox_write
+            .account_recovery_verify(
+                &AccountRecoveryVerifyEvent::new(test_source(), begin.recovery_id, &message.code),
+                ct,
+            )
+            .expect("Failed to verify account recovery");
+        assert!(matches!(result, AccountRecoveryVerify::Success(..)));
+        assert!(idms_prox_write.commit().is_ok());
+
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::AccountRecoveryCompleted { uuid, .. }) => {
+                assert_eq!(uuid, TESTPERSON_UUID);
+            }
+            _ => panic!("Oh no"),
+        }
+
+        // The code can not be reused.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let result = idms_prox_write
+            .account_recovery_verify(
+                &AccountRecoveryVerifyEvent::new(test_source(), begin.recovery_id, &message.code),
+                ct,
+            )
+            .expect("Failed to verify account recovery");
+        assert!(matches!(result, AccountRecoveryVerify::Expired));
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    #[idm_test(audit = 1)]
+    async fn test_idm_account_recovery_rate_limit(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+        idms_audit: &mut IdmServerAudit,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        setup_test_person(idms, ct, true).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let first = idms_prox_write
+            .account_recovery_begin(
+                &AccountRecoveryBeginEvent::new(test_source(), "testperson"),
+                ct,
+            )
+            .expect("Failed to begin account recovery");
+        assert!(first.message.is_some());
+
+        // A second request is refused until the interval has passed.
+        let second = idms_prox_write
+            .account_recovery_begin(
+                &AccountRecoveryBeginEvent::new(test_source(), "testperson"),
+                ct + Duration::from_secs(1),
+            )
+            .expect("Failed to begin account recovery");
+        assert!(second.message.is_none());
+        assert_eq!(second.recovery_id, first.recovery_id);
+        assert!(idms_prox_write.commit().is_ok());
+
+        assert!(matches!(
+            idms_audit.audit_rx().try_recv(),
+            Ok(AuditEvent::AccountRecoveryRequested { .. })
+        ));
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::AccountRecoveryDenied { reason, .. }) => {
+                assert_eq!(reason, AccountRecoveryDeniedReason::RateLimited);
+            }
+            _ => panic!("Oh no"),
+        }
+
+        // Once the interval has passed a new code is issued, and the first is superseded.
+        let ct = ct + ACCOUNT_RECOVERY_REQUEST_INTERVAL;
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let third = idms_prox_write
+            .account_recovery_begin(
+                &AccountRecoveryBeginEvent::new(test_source(), "testperson"),
+                ct,
+            )
+            .expect("Failed to begin account recovery");
+        assert!(third.message.is_some());
+
+        let first_code = first.message.expect("No recovery message was issued").code;
+        let result = idms_prox_write
+            .account_recovery_verify(
+                &AccountRecoveryVerifyEvent::new(test_source(), first.recovery_id, &first_code),
+                ct,
+            )
+            .expect("Failed to verify account recovery");
+        assert!(matches!(result, AccountRecoveryVerify::Expired));
+
+        // Too many incorrect attempts abandon the recovery.
+        for _ in 1..ACCOUNT_RECOVERY_MAX_ATTEMPTS {
+            let result = idms_prox_write
+                .account_recovery_verify(
+                    &AccountRecoveryVerifyEvent::new(test_source(), third.recovery_id, "nope"),
+                    ct,
+                )
+                .expect("Failed to verify account recovery");
+            assert!(matches!(result, AccountRecoveryVerify::InvalidCode));
+        }
+        let result = idms_prox_write
+            .account_recovery_verify(
+                &AccountRecoveryVerifyEvent::new(test_source(), third.recovery_id, "nope"),
+                ct,
+            )
+            .expect("Failed to verify account recovery");
+        assert!(matches!(result, AccountRecoveryVerify::Expired));
+
+        let third_code = third.message.expect("No recovery message was issued").code;
+        let result = idms_prox_write
+            .account_recovery_verify(
+                &AccountRecoveryVerifyEvent::new(test_source(), third.recovery_id, &third_code),
+                ct,
+            )
+            .expect("Failed to verify account recovery");
+        assert!(matches!(result, AccountRecoveryVerify::Expired));
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    #[idm_test]
+    async fn test_idm_account_recovery_no_disclosure(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        setup_test_person(idms, ct, false).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        // Neither a missing account, an account without mail, nor a service account
+        // are issued a code, but all are given a recovery id.
+        for name in ["nonexistent", "testperson", "admin"] {
+            let begin = idms_prox_write
+                .account_recovery_begin(&AccountRecoveryBeginEvent::new(test_source(), name), ct)
+                .expect("Failed to begin account recovery");
+            assert!(begin.message.is_none());
+
+            let result = idms_prox_write
+                .account_recovery_verify(
+                    &AccountRecoveryVerifyEvent::new(test_source(), begin.recovery_id, "00000000"),
+                    ct,
+                )
+                .expect("Failed to verify account recovery");
+            assert!(matches!(result, AccountRecoveryVerify::Expired));
+        }
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+}
@@ -0,0 +1,378 @@
+//! Persons may issue api tokens to themself, for example to allow scripts and developer tools
+//! to act on their behalf. This is only allowed if their account policy permits it, and the
+//! person must have a read-write session to issue or revoke a token. A token can never be
+//! used to issue further tokens.
+
+use std::time::Duration;
+
+use compact_jwt::{Jws, JwsCompact};
+use kanidm_proto::internal::{
+    ApiToken as ProtoApiToken, ApiTokenDetail as ProtoApiTokenDetail,
+    SelfApiTokens as ProtoSelfApiTokens,
+};
+use time::OffsetDateTime;
+
+use crate::idm::account::Account;
+use crate::idm::audit::AuditEvent;
+use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction};
+use crate::prelude::*;
+use crate::value::{ApiToken, CredentialFactor};
+
+pub struct GenerateSelfApiTokenEvent {
+    // Who initiated this? The token is issued to this person.
+    pub ident: Identity,
+    // The label
+    pub label: String,
+    // When should this expire?
+    pub expiry: Option<OffsetDateTime>,
+    // Is this a read-write token?
+    pub read_write: bool,
+}
+
+pub struct DestroySelfApiTokenEvent {
+    // Who initiated this? The token must belong to this person.
+    pub ident: Identity,
+    // Which token id.
+    pub token_id: Uuid,
+}
+
+/// Resolve the person that the identity represents, checking that they did not authenticate
+/// with one of their own api tokens.
+fn self_api_token_person(
+    ident: &Identity,
+    entry: &Entry<EntrySealed, EntryCommitted>,
+) -> Result<(), OperationError> {
+    if !entry.attribute_equality(Attribute::Class, &EntryClass::Person.into()) {
+        error!("Only persons may manage their own api tokens");
+        return Err(OperationError::AccessDenied);
+    }
+
+    let session_id = ident.get_session_id();
+    let is_api_token = entry
+        .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+        .map(|api_tokens| api_tokens.contains_key(&session_id))
+        .unwrap_or(false);
+
+    if is_api_token {
+        error!("Api tokens may not be used to manage api tokens");
+        return Err(OperationError::AccessDenied);
+    }
+
+    Ok(())
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub fn account_generate_self_api_token(
+        &mut self,
+        gte: &GenerateSelfApiTokenEvent,
+        ct: Duration,
+    ) -> Result<JwsCompact, OperationError> {
+        let Some(target) = gte.ident.get_uuid() else {
+            error!("Only persons may issue their own api tokens");
+            return Err(OperationError::AccessDenied);
+        };
+
+        self.qs_write.check_maintenance_mode(&gte.ident)?;
+
+        if gte.ident.access_scope() != AccessScope::ReadWrite {
+            error!("Issuing an api token requires a read-write session");
+            return Err(OperationError::AccessDenied);
+        }
+
+        if gte.label.trim().is_empty() {
+            error!("Api token label must not be empty");
+            return Err(OperationError::InvalidAttribute(
+                Attribute::ApiTokenSession.to_string(),
+            ));
+        }
+
+        let entry = self.qs_write.internal_search_uuid(target)?;
+        self_api_token_person(&gte.ident, &entry)?;
+
+        let (_account, rap) = Account::try_from_entry_with_policy(&entry, &mut self.qs_write)?;
+
+        if !rap.allow_api_tokens() {
+            security_info!("Account policy does not allow this person to issue api tokens");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let session_id = Uuid::new_v4();
+        let issued_at = OffsetDateTime::UNIX_EPOCH + ct;
+
+        // Normalise to UTC in case it was provided as something else.
+        let expiry = gte.expiry.map(|odt| odt.to_offset(time::UtcOffset::UTC));
+
+        if expiry.is_some_and(|expiry| expiry <= issued_at) {
+            error!("Api token expiry must be in the future");
+            return Err(OperationError::InvalidRequestState);
+        }
+
+        let scope = if gte.read_write {
+            ApiTokenScope::ReadWrite
+        } else {
+            ApiTokenScope::ReadOnly
+        };
+        let purpose = scope.try_into()?;
+
+        let session = Value::ApiToken(
+            session_id,
+            ApiToken {
+                label: gte.label.clone(),
+                expiry,
+                issued_at,
+                issued_by: gte.ident.get_event_origin_id(),
+                scope,
+                permissions: Vec::with_capacity(0),
+            },
+        );
+
+        let proto_api_token = ProtoApiToken {
+            account_id: target,
+            token_id: session_id,
+            label: gte.label.clone(),
+            expiry,
+            issued_at,
+            purpose,
+            permissions: Vec::with_capacity(0),
+        };
+
+        let token = Jws::into_json(&proto_api_token).map_err(|err| {
+            error!(?err, "Unable to serialise JWS");
+            OperationError::SerdeJsonError
+        })?;
+
+        // Persons can't write api token sessions through access controls, since that would
+        // bypass the account policy. The checks above have established this is their own entry.
+        self.qs_write
+            .internal_modify_uuid(
+                target,
+                &ModifyList::new_list(vec![Modify::Present(Attribute::ApiTokenSession, session)]),
+            )
+            .map_err(|err| {
+                error!(?err, "Failed to generate api token");
+                err
+            })?;
+
+        self.queue_activity_event(AuditEvent::ApiTokenIssued {
+            source: gte.ident.source().clone().into(),
+            actor: gte.ident.get_uuid(),
+            uuid: target,
+            token_id: session_id,
+            label: gte.label.clone(),
+            expiry,
+            time: issued_at,
+        });
+
+        self.qs_write
+            .get_domain_key_object_handle()?
+            .jws_es256_sign(&token, ct)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub fn account_destroy_self_api_token(
+        &mut self,
+        dte: &DestroySelfApiTokenEvent,
+    ) -> Result<(), OperationError> {
+        let Some(target) = dte.ident.get_uuid() else {
+            error!("Only persons may revoke their own api tokens");
+            return Err(OperationError::AccessDenied);
+        };
+
+        if dte.ident.access_scope() != AccessScope::ReadWrite {
+            error!("Revoking an api token requires a read-write session");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let entry = self.qs_write.internal_search_uuid(target)?;
+        self_api_token_person(&dte.ident, &entry)?;
+
+        let token_exists = entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .map(|api_tokens| api_tokens.contains_key(&dte.token_id))
+            .unwrap_or(false);
+
+        if !token_exists {
+            error!(token_id = %dte.token_id, "Api token does not exist");
+            return Err(OperationError::NoMatchingEntries);
+        }
+
+        // Revoking is always allowed, even if the account policy no longer allows new tokens.
+        let modlist = ModifyList::new_list(vec![
+            Modify::Removed(
+                Attribute::ApiTokenSession,
+                PartialValue::Refer(dte.token_id),
+            ),
+            Modify::Removed(Attribute::CredentialUsage, PartialValue::Uuid(dte.token_id)),
+        ]);
+
+        self.qs_write
+            .internal_modify_uuid(target, &modlist)
+            .map_err(|e| {
+                admin_error!("Failed to destroy api token {:?}", e);
+                e
+            })
+    }
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub fn account_list_self_api_tokens(
+        &mut self,
+        ident: &Identity,
+    ) -> Result<ProtoSelfApiTokens, OperationError> {
+        let Some(target) = ident.get_uuid() else {
+            error!("Only persons may list their own api tokens");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let entry = self.qs_read.internal_search_uuid(target)?;
+
+        if !entry.attribute_equality(Attribute::Class, &EntryClass::Person.into()) {
+            error!("Only persons may list their own api tokens");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let (_account, rap) = Account::try_from_entry_with_policy(&entry, &mut self.qs_read)?;
+
+        let usage = entry.get_ava_as_credential_usage_map(Attribute::CredentialUsage);
+
+        let tokens = entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .into_iter()
+            .flat_map(|api_tokens| api_tokens.iter())
+            .map(|(token_id, token)| {
+                let purpose = token.scope.try_into().inspect_err(|err| {
+                    admin_error!(?err, "Invalid api_token {}", token_id);
+                })?;
+
+                let last_used = usage
+                    .and_then(|usage| usage.get(&(*token_id, CredentialFactor::ApiToken)))
+                    .map(|usage| usage.last_used);
+
+                Ok(ProtoApiTokenDetail {
+                    token: ProtoApiToken {
+                        account_id: target,
+                        token_id: *token_id,
+                        label: token.label.clone(),
+                        expiry: token.expiry,
+                        issued_at: token.issued_at,
+                        purpose,
+                        permissions: token.permissions.iter().map(|p| p.into()).collect(),
+                    },
+                    last_used,
+                })
+            })
+            .collect::<Result<Vec<_>, OperationError>>()?;
+
+        Ok(ProtoSelfApiTokens {
+            allowed: rap.allow_api_tokens(),
+            tokens,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{DestroySelfApiTokenEvent, GenerateSelfApiTokenEvent};
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[idm_test]
+    async fn test_idm_self_api_token(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let target_uuid = Uuid::new_v4();
+
+        let e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Uuid, Value::Uuid(target_uuid)),
+            (Attribute::Name, Value::new_iname("kevin")),
+            (Attribute::DisplayName, Value::new_utf8s("Kevin"))
+        );
+
+        assert!(idms_prox_write.qs_write.internal_create(vec![e]).is_ok());
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(target_uuid)
+            .expect("Unable to find person");
+        let ident = Identity::from_impersonate_entry_readwrite(entry);
+
+        let gte = GenerateSelfApiTokenEvent {
+            ident: ident.clone(),
+            label: "laptop".to_string(),
+            expiry: None,
+            read_write: false,
+        };
+
+        // Denied by default.
+        assert!(matches!(
+            idms_prox_write.account_generate_self_api_token(&gte, ct),
+            Err(OperationError::AccessDenied)
+        ));
+
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_ALL_ACCOUNTS,
+                &ModifyList::new_purge_and_set(Attribute::AllowApiTokens, Value::new_bool(true)),
+            )
+            .expect("Unable to allow api tokens");
+
+        idms_prox_write
+            .account_generate_self_api_token(&gte, ct)
+            .expect("Unable to generate api token");
+
+        // A read only session can't issue tokens.
+        let gte_ro = GenerateSelfApiTokenEvent {
+            ident: ident.project_with_scope(AccessScope::ReadOnly),
+            label: "laptop".to_string(),
+            expiry: None,
+            read_write: false,
+        };
+
+        assert!(matches!(
+            idms_prox_write.account_generate_self_api_token(&gte_ro, ct),
+            Err(OperationError::AccessDenied)
+        ));
+
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let self_tokens = idms_prox_read
+            .account_list_self_api_tokens(&ident)
+            .expect("Unable to list api tokens");
+        drop(idms_prox_read);
+
+        assert!(self_tokens.allowed);
+        assert_eq!(self_tokens.tokens.len(), 1);
+        let token_id = self_tokens.tokens[0].token.token_id;
+        assert!(self_tokens.tokens[0].last_used.is_none());
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let dte = DestroySelfApiTokenEvent {
+            ident: ident.clone(),
+            token_id,
+        };
+
+        idms_prox_write
+            .account_destroy_self_api_token(&dte)
+            .expect("Unable to destroy api token");
+
+        // It's gone now.
+        assert_eq!(
+            idms_prox_write.account_destroy_self_api_token(&dte),
+            Err(OperationError::NoMatchingEntries)
+        );
+
+        idms_prox_write.commit().expect("Failed to commit txn");
+    }
+}
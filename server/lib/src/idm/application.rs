@@ -1,11 +1,14 @@
 use super::ldap::{LdapBoundToken, LdapSession};
 use crate::idm::account::Account;
 use crate::idm::event::LdapApplicationAuthEvent;
-use crate::idm::server::{IdmServerAuthTransaction, IdmServerTransaction};
+use crate::idm::server::{
+    IdmServerAuthTransaction, IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction,
+    IdmServerTransaction,
+};
 use crate::prelude::*;
 use concread::cowcell::*;
 use hashbrown::HashMap;
-use kanidm_proto::internal::OperationError;
+use kanidm_proto::internal::{ApplicationPassword as ProtoApplicationPassword, OperationError};
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -240,12 +243,195 @@ impl GenerateApplicationPasswordEvent {
     }
 }
 
+pub struct GenerateSelfApplicationPasswordEvent {
+    // Who initiated this? The password is generated for this person.
+    pub ident: Identity,
+    // The name of the application.
+    pub application: String,
+    // The label
+    pub label: String,
+}
+
+pub struct RevokeSelfApplicationPasswordEvent {
+    // Who initiated this? The password must belong to this person.
+    pub ident: Identity,
+    // Which password.
+    pub password_id: Uuid,
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub fn list_self_application_passwords(
+        &mut self,
+        ident: &Identity,
+    ) -> Result<Vec<ProtoApplicationPassword>, OperationError> {
+        let Some(target) = ident.get_uuid() else {
+            error!("Only persons may list their own application passwords");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let entry = self.qs_read.internal_search_uuid(target)?;
+
+        let Some(apps_pwds) = entry.get_ava_application_password(Attribute::ApplicationPassword)
+        else {
+            return Ok(Vec::with_capacity(0));
+        };
+
+        let mut app_pwds = Vec::with_capacity(apps_pwds.values().map(Vec::len).sum());
+
+        for (app_uuid, pwds) in apps_pwds.iter() {
+            // Passwords are removed with their application, so this only falls back to the
+            // uuid if the application can't be read.
+            let application = self
+                .qs_read
+                .internal_search_uuid(*app_uuid)
+                .ok()
+                .and_then(|app| {
+                    app.get_ava_single_iname(Attribute::Name)
+                        .map(str::to_string)
+                })
+                .unwrap_or_else(|| app_uuid.to_string());
+
+            app_pwds.extend(pwds.iter().map(|ap| ProtoApplicationPassword {
+                uuid: ap.uuid,
+                application: application.clone(),
+                label: ap.label.clone(),
+            }));
+        }
+
+        app_pwds
+            .sort_unstable_by(|a, b| (&a.application, &a.label).cmp(&(&b.application, &b.label)));
+
+        Ok(app_pwds)
+    }
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub fn generate_self_application_password(
+        &mut self,
+        gae: &GenerateSelfApplicationPasswordEvent,
+    ) -> Result<String, OperationError> {
+        let Some(target) = gae.ident.get_uuid() else {
+            error!("Only persons may generate their own application passwords");
+            return Err(OperationError::AccessDenied);
+        };
+
+        if gae.ident.access_scope() != AccessScope::ReadWrite {
+            error!("Generating an application password requires a read-write session");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let label = gae.label.trim();
+        if label.is_empty() {
+            error!("Application password label must not be empty");
+            return Err(OperationError::InvalidAttribute(
+                Attribute::ApplicationPassword.to_string(),
+            ));
+        }
+
+        let application = self.qs_write.name_to_uuid(&gae.application)?;
+        let app_entry = self.qs_write.internal_search_uuid(application)?;
+
+        if !app_entry.attribute_equality(Attribute::Class, &EntryClass::Application.into()) {
+            error!(application = %gae.application, "Entry is not an application");
+            return Err(OperationError::NoMatchingEntries);
+        }
+
+        let linked_group = app_entry
+            .get_ava_single_refer(Attribute::LinkedGroup)
+            .ok_or(OperationError::InvalidValueState)?;
+
+        // The password could never be used to bind if the person isn't a member of the
+        // linked group, so don't issue one.
+        let is_memberof = self
+            .qs_write
+            .internal_search_uuid(target)?
+            .get_ava_refer(Attribute::MemberOf)
+            .map(|member_of_set| member_of_set.contains(&linked_group))
+            .unwrap_or_default();
+
+        if !is_memberof {
+            error!(
+                application = %gae.application,
+                "Person is not a member of the application linked group"
+            );
+            return Err(OperationError::AccessDenied);
+        }
+
+        // The password is generated, so the password quality and credential type rules of
+        // the account policy don't apply to it. Access controls still decide if the person
+        // may write their application passwords.
+        let ev = GenerateApplicationPasswordEvent {
+            ident: gae.ident.clone(),
+            target,
+            application,
+            label: label.to_string(),
+        };
+
+        self.generate_application_password(&ev)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub fn revoke_self_application_password(
+        &mut self,
+        rae: &RevokeSelfApplicationPasswordEvent,
+    ) -> Result<(), OperationError> {
+        let Some(target) = rae.ident.get_uuid() else {
+            error!("Only persons may revoke their own application passwords");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let password_exists = self
+            .qs_write
+            .internal_search_uuid(target)?
+            .get_ava_application_password(Attribute::ApplicationPassword)
+            .map(|apps_pwds| {
+                apps_pwds
+                    .values()
+                    .flatten()
+                    .any(|ap| ap.uuid == rae.password_id)
+            })
+            .unwrap_or(false);
+
+        if !password_exists {
+            error!(password_id = %rae.password_id, "Application password does not exist");
+            return Err(OperationError::NoMatchingEntries);
+        }
+
+        let modlist = ModifyList::new_remove(
+            Attribute::ApplicationPassword,
+            PartialValue::Uuid(rae.password_id),
+        );
+
+        self.qs_write
+            .impersonate_modify(
+                // Filter as executed
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(target))),
+                // Filter as intended (acp)
+                &filter_all!(f_eq(Attribute::Uuid, PartialValue::Uuid(target))),
+                &modlist,
+                // Provide the event to impersonate
+                &rae.ident,
+            )
+            .map_err(|err| {
+                error!(?err, "Failed to revoke application password");
+                err
+            })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::event::CreateEvent;
     use crate::idm::account::Account;
     use crate::idm::application::Application;
-    use crate::idm::application::GenerateApplicationPasswordEvent;
+    use crate::idm::application::{
+        GenerateApplicationPasswordEvent, GenerateSelfApplicationPasswordEvent,
+        RevokeSelfApplicationPasswordEvent,
+    };
+    use crate::idm::delayed::DelayedAction;
+    use crate::idm::event::LdapApplicationAuthEvent;
     use crate::idm::server::IdmServerTransaction;
     use crate::idm::serviceaccount::{DestroyApiTokenEvent, GenerateApiTokenEvent};
     use crate::prelude::*;
@@ -624,10 +810,7 @@ mod tests {
 
     // Test apitoken for application entries
     #[idm_test]
-    async fn test_idm_application_api_token(
-        idms: &IdmServer,
-        _idms_delayed: &mut IdmServerDelayed,
-    ) {
+    async fn test_idm_application_api_token(idms: &IdmServer, idms_delayed: &mut IdmServerDelayed) {
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
         let past_grc = Duration::from_secs(TEST_CURRENT_TIME + 1) + AUTH_TOKEN_GRACE_WINDOW;
         let exp = Duration::from_secs(TEST_CURRENT_TIME + 6000);
@@ -712,5 +895,174 @@ mod tests {
         );
 
         assert!(idms_prox_write.commit().is_ok());
+
+        // Each use of the api token is recorded.
+        while let Ok(da) = idms_delayed.try_recv() {
+            assert!(matches!(da, DelayedAction::ApiTokenUse(_)));
+        }
+    }
+
+    #[idm_test]
+    async fn test_idm_self_application_password(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let test_usr_uuid = Uuid::new_v4();
+        let test_grp_uuid = Uuid::new_v4();
+        let test_app_uuid = Uuid::new_v4();
+        let other_grp_uuid = Uuid::new_v4();
+        let other_app_uuid = Uuid::new_v4();
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        // The person has no primary credential, application passwords don't depend on it.
+        let e_usr = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testuser1")),
+            (Attribute::Uuid, Value::Uuid(test_usr_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("testuser1"))
+        );
+
+        let e_grp = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup1")),
+            (Attribute::Uuid, Value::Uuid(test_grp_uuid)),
+            (Attribute::Member, Value::Refer(test_usr_uuid))
+        );
+
+        let e_app = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Class, EntryClass::Application.to_value()),
+            (Attribute::Name, Value::new_iname("mail")),
+            (Attribute::Uuid, Value::Uuid(test_app_uuid)),
+            (Attribute::LinkedGroup, Value::Refer(test_grp_uuid))
+        );
+
+        let e_other_grp = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup2")),
+            (Attribute::Uuid, Value::Uuid(other_grp_uuid))
+        );
+
+        let e_other_app = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Class, EntryClass::Application.to_value()),
+            (Attribute::Name, Value::new_iname("calendar")),
+            (Attribute::Uuid, Value::Uuid(other_app_uuid)),
+            (Attribute::LinkedGroup, Value::Refer(other_grp_uuid))
+        );
+
+        let ce = CreateEvent::new_internal(vec![e_usr, e_grp, e_app, e_other_grp, e_other_app]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(test_usr_uuid)
+            .expect("Unable to find person");
+        let ident = Identity::from_impersonate_entry_readwrite(entry.clone());
+
+        // A read only session can't generate a password.
+        let gae = GenerateSelfApplicationPasswordEvent {
+            ident: Identity::from_impersonate_entry_readonly(entry),
+            application: "mail".to_string(),
+            label: "laptop".to_string(),
+        };
+        assert_eq!(
+            idms_prox_write.generate_self_application_password(&gae),
+            Err(OperationError::AccessDenied)
+        );
+
+        // The person isn't a member of the linked group of this application.
+        let gae = GenerateSelfApplicationPasswordEvent {
+            ident: ident.clone(),
+            application: "calendar".to_string(),
+            label: "laptop".to_string(),
+        };
+        assert_eq!(
+            idms_prox_write.generate_self_application_password(&gae),
+            Err(OperationError::AccessDenied)
+        );
+
+        let gae = GenerateSelfApplicationPasswordEvent {
+            ident: ident.clone(),
+            application: "mail".to_string(),
+            label: "laptop".to_string(),
+        };
+        let laptop_pw = idms_prox_write
+            .generate_self_application_password(&gae)
+            .expect("Failed to generate application password");
+
+        let gae = GenerateSelfApplicationPasswordEvent {
+            ident: ident.clone(),
+            application: "mail".to_string(),
+            label: "phone".to_string(),
+        };
+        let phone_pw = idms_prox_write
+            .generate_self_application_password(&gae)
+            .expect("Failed to generate application password");
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let app_pwds = idms_prox_read
+            .list_self_application_passwords(&ident)
+            .expect("Unable to list application passwords");
+        drop(idms_prox_read);
+
+        assert_eq!(app_pwds.len(), 2);
+        assert!(app_pwds.iter().all(|ap| ap.application == "mail"));
+        assert_eq!(app_pwds[0].label, "laptop");
+        assert_eq!(app_pwds[1].label, "phone");
+
+        // Both passwords can bind.
+        for pw in [&laptop_pw, &phone_pw] {
+            let mut idms_auth = idms.auth().await.unwrap();
+            let lae = LdapApplicationAuthEvent::new("mail", test_usr_uuid, pw.clone())
+                .expect("Failed to build event");
+            let bound = idms_auth
+                .application_auth_ldap(&lae, ct)
+                .await
+                .expect("Failed to bind");
+            assert!(bound.is_some());
+        }
+
+        // Revoke only the laptop password.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let rae = RevokeSelfApplicationPasswordEvent {
+            ident: ident.clone(),
+            password_id: app_pwds[0].uuid,
+        };
+        assert!(idms_prox_write
+            .revoke_self_application_password(&rae)
+            .is_ok());
+        assert_eq!(
+            idms_prox_write.revoke_self_application_password(&rae),
+            Err(OperationError::NoMatchingEntries)
+        );
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_auth = idms.auth().await.unwrap();
+        let lae = LdapApplicationAuthEvent::new("mail", test_usr_uuid, laptop_pw)
+            .expect("Failed to build event");
+        assert!(idms_auth
+            .application_auth_ldap(&lae, ct)
+            .await
+            .expect("Failed to bind")
+            .is_none());
+
+        let lae = LdapApplicationAuthEvent::new("mail", test_usr_uuid, phone_pw)
+            .expect("Failed to build event");
+        assert!(idms_auth
+            .application_auth_ldap(&lae, ct)
+            .await
+            .expect("Failed to bind")
+            .is_some());
     }
 }
@@ -1,9 +1,18 @@
+use crate::idm::geoip::{GeoLocation, SessionAnomaly};
 use crate::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::net::IpAddr;
 use time::OffsetDateTime;
+use tokio::sync::mpsc::UnboundedSender as Sender;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+/// The version of the audit record schema. This must be incremented if the shape of
+/// any existing event is changed so that downstream consumers can detect the change.
+/// Adding new events does not require a version change.
+pub const AUDIT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "address", rename_all = "snake_case")]
 pub enum AuditSource {
     Internal,
     Https(IpAddr),
@@ -20,13 +29,475 @@ impl From<Source> for AuditSource {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountRecoveryDeniedReason {
+    RateLimited,
+    InvalidCode,
+    AttemptsExceeded,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum AuditEvent {
     AuthenticationDenied {
         source: AuditSource,
         uuid: Uuid,
         spn: String,
-        #[serde(with = "time::serde::timestamp")]
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    Oauth2ClientCredentialsGranted {
+        uuid: Uuid,
+        client_id: String,
+        session_id: Uuid,
+        scopes: BTreeSet<String>,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    Oauth2TokenExchanged {
+        /// The user that the token was exchanged on behalf of.
+        uuid: Uuid,
+        /// The client that exchanged the token, which is the acting party of the new token.
+        client_id: String,
+        /// The client that the new token was issued for.
+        audience: String,
+        subject_session_id: Uuid,
+        session_id: Uuid,
+        scopes: BTreeSet<String>,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    ReplicationClockSkew {
+        partner: String,
+        /// The estimated offset of the partner's clock from ours. Positive values
+        /// indicate the partner is ahead of us.
+        skew_ms: i64,
+        tolerance_exceeded: bool,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    AccountRecoveryRequested {
+        source: AuditSource,
+        uuid: Uuid,
+        spn: String,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    AccountRecoveryDenied {
+        source: AuditSource,
+        uuid: Uuid,
+        spn: String,
+        reason: AccountRecoveryDeniedReason,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    AccountRecoveryCompleted {
+        source: AuditSource,
+        uuid: Uuid,
+        spn: String,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    AuthenticationSucceeded {
+        source: AuditSource,
+        uuid: Uuid,
+        spn: String,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    ApiTokenIssued {
+        source: AuditSource,
+        /// The identity that requested the token.
+        actor: Option<Uuid>,
+        uuid: Uuid,
+        token_id: Uuid,
+        label: String,
+        #[serde(default, with = "time::serde::rfc3339::option")]
+        expiry: Option<OffsetDateTime>,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    CredentialUpdated {
+        uuid: Uuid,
+        spn: String,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    EntriesCreated {
+        source: AuditSource,
+        actor: Uuid,
+        targets: BTreeSet<Uuid>,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    EntriesModified {
+        source: AuditSource,
+        actor: Uuid,
+        targets: BTreeSet<Uuid>,
+        attrs: BTreeSet<Attribute>,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    EntriesDeleted {
+        source: AuditSource,
+        actor: Uuid,
+        targets: BTreeSet<Uuid>,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// An account was given an expiry by the lifecycle policy of a group it is a member of.
+    AccountLifecycleExpirySet {
+        uuid: Uuid,
+        spn: String,
+        #[serde(with = "time::serde::rfc3339")]
+        expiry: OffsetDateTime,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// An expired account was moved to the recycle bin by the lifecycle policy of a group it
+    /// is a member of.
+    AccountLifecycleArchived {
+        uuid: Uuid,
+        spn: String,
+        #[serde(with = "time::serde::rfc3339")]
+        expiry: OffsetDateTime,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// An ssh user certificate was issued to an account by the ssh certificate authority.
+    SshCertificateIssued {
+        source: AuditSource,
+        uuid: Uuid,
+        spn: String,
+        serial: u64,
+        principals: Vec<String>,
+        #[serde(with = "time::serde::rfc3339")]
+        valid_before: OffsetDateTime,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// A kerberos service ticket was issued to an account that logged in to a host.
+    KerberosTicketIssued {
+        source: AuditSource,
+        uuid: Uuid,
+        spn: String,
+        host: String,
+        service: String,
+        #[serde(with = "time::serde::rfc3339")]
+        end_time: OffsetDateTime,
+        #[serde(with = "time::serde::rfc3339")]
         time: OffsetDateTime,
     },
+    /// A scheduled report was generated and sent to the members of its recipient groups.
+    ReportGenerated {
+        report: String,
+        kind: String,
+        rows: usize,
+        recipients: Vec<String>,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// A session was issued to the admin account or an emergency account by break glass
+    /// authentication.
+    BreakGlassAuthenticated {
+        source: AuditSource,
+        uuid: Uuid,
+        spn: String,
+        session_id: Uuid,
+        #[serde(with = "time::serde::rfc3339")]
+        expiry: OffsetDateTime,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// A member of idm_impersonation_admins was issued a session that impersonates a person.
+    ImpersonationSessionIssued {
+        source: AuditSource,
+        /// The member of idm_impersonation_admins that requested the session.
+        actor: Uuid,
+        uuid: Uuid,
+        spn: String,
+        session_id: Uuid,
+        #[serde(with = "time::serde::rfc3339")]
+        expiry: OffsetDateTime,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// A break glass authentication was attempted, but the challenge was not signed by the
+    /// break glass key, or the recovery code was not valid.
+    BreakGlassDenied {
+        source: AuditSource,
+        /// The emergency account a recovery code was presented for, if any.
+        account: Option<String>,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// An account requested to join a group that accepts membership requests.
+    GroupMembershipRequested {
+        source: AuditSource,
+        uuid: Uuid,
+        spn: String,
+        group: Uuid,
+        request_id: Uuid,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// A request to join a group was approved or denied by one of the entry managers of the
+    /// group.
+    GroupMembershipRequestDecided {
+        source: AuditSource,
+        actor: Uuid,
+        /// The account that made the request.
+        uuid: Uuid,
+        group: Uuid,
+        request_id: Uuid,
+        approved: bool,
+        #[serde(default, with = "time::serde::rfc3339::option")]
+        valid_until: Option<OffsetDateTime>,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+    /// An account signed in from an unusual location, such as a country it has not signed in
+    /// from before.
+    SessionAnomaly {
+        source: AuditSource,
+        uuid: Uuid,
+        spn: String,
+        anomaly: SessionAnomaly,
+        #[serde(with = "time::serde::rfc3339")]
+        time: OffsetDateTime,
+    },
+}
+
+impl AuditEvent {
+    /// The stable name of this event, as it appears in the `event` field of the record.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditEvent::AuthenticationDenied { .. } => "authentication_denied",
+            AuditEvent::Oauth2ClientCredentialsGranted { .. } => {
+                "oauth2_client_credentials_granted"
+            }
+            AuditEvent::Oauth2TokenExchanged { .. } => "oauth2_token_exchanged",
+            AuditEvent::ReplicationClockSkew { .. } => "replication_clock_skew",
+            AuditEvent::AccountRecoveryRequested { .. } => "account_recovery_requested",
+            AuditEvent::AccountRecoveryDenied { .. } => "account_recovery_denied",
+            AuditEvent::AccountRecoveryCompleted { .. } => "account_recovery_completed",
+            AuditEvent::AuthenticationSucceeded { .. } => "authentication_succeeded",
+            AuditEvent::ApiTokenIssued { .. } => "api_token_issued",
+            AuditEvent::CredentialUpdated { .. } => "credential_updated",
+            AuditEvent::EntriesCreated { .. } => "entries_created",
+            AuditEvent::EntriesModified { .. } => "entries_modified",
+            AuditEvent::EntriesDeleted { .. } => "entries_deleted",
+            AuditEvent::AccountLifecycleExpirySet { .. } => "account_lifecycle_expiry_set",
+            AuditEvent::AccountLifecycleArchived { .. } => "account_lifecycle_archived",
+            AuditEvent::SshCertificateIssued { .. } => "ssh_certificate_issued",
+            AuditEvent::KerberosTicketIssued { .. } => "kerberos_ticket_issued",
+            AuditEvent::ReportGenerated { .. } => "report_generated",
+            AuditEvent::BreakGlassAuthenticated { .. } => "break_glass_authenticated",
+            AuditEvent::ImpersonationSessionIssued { .. } => "impersonation_session_issued",
+            AuditEvent::BreakGlassDenied { .. } => "break_glass_denied",
+            AuditEvent::GroupMembershipRequested { .. } => "group_membership_requested",
+            AuditEvent::GroupMembershipRequestDecided { .. } => "group_membership_request_decided",
+            AuditEvent::SessionAnomaly { .. } => "session_anomaly",
+        }
+    }
+
+    pub fn time(&self) -> OffsetDateTime {
+        match self {
+            AuditEvent::AuthenticationDenied { time, .. }
+            | AuditEvent::Oauth2ClientCredentialsGranted { time, .. }
+            | AuditEvent::Oauth2TokenExchanged { time, .. }
+            | AuditEvent::ReplicationClockSkew { time, .. }
+            | AuditEvent::AccountRecoveryRequested { time, .. }
+            | AuditEvent::AccountRecoveryDenied { time, .. }
+            | AuditEvent::AccountRecoveryCompleted { time, .. }
+            | AuditEvent::AuthenticationSucceeded { time, .. }
+            | AuditEvent::ApiTokenIssued { time, .. }
+            | AuditEvent::CredentialUpdated { time, .. }
+            | AuditEvent::EntriesCreated { time, .. }
+            | AuditEvent::EntriesModified { time, .. }
+            | AuditEvent::EntriesDeleted { time, .. }
+            | AuditEvent::AccountLifecycleExpirySet { time, .. }
+            | AuditEvent::AccountLifecycleArchived { time, .. }
+            | AuditEvent::SshCertificateIssued { time, .. }
+            | AuditEvent::KerberosTicketIssued { time, .. }
+            | AuditEvent::ReportGenerated { time, .. }
+            | AuditEvent::BreakGlassAuthenticated { time, .. }
+            | AuditEvent::ImpersonationSessionIssued { time, .. }
+            | AuditEvent::BreakGlassDenied { time, .. }
+            | AuditEvent::GroupMembershipRequested { time, .. }
+            | AuditEvent::GroupMembershipRequestDecided { time, .. }
+            | AuditEvent::SessionAnomaly { time, .. } => *time,
+        }
+    }
+
+    /// The address that the event came from, if it came from a client.
+    pub fn source_ip(&self) -> Option<IpAddr> {
+        match self {
+            AuditEvent::AuthenticationDenied { source, .. }
+            | AuditEvent::AccountRecoveryRequested { source, .. }
+            | AuditEvent::AccountRecoveryDenied { source, .. }
+            | AuditEvent::AccountRecoveryCompleted { source, .. }
+            | AuditEvent::AuthenticationSucceeded { source, .. }
+            | AuditEvent::ApiTokenIssued { source, .. }
+            | AuditEvent::EntriesCreated { source, .. }
+            | AuditEvent::EntriesModified { source, .. }
+            | AuditEvent::EntriesDeleted { source, .. }
+            | AuditEvent::SshCertificateIssued { source, .. }
+            | AuditEvent::KerberosTicketIssued { source, .. }
+            | AuditEvent::BreakGlassAuthenticated { source, .. }
+            | AuditEvent::ImpersonationSessionIssued { source, .. }
+            | AuditEvent::BreakGlassDenied { source, .. }
+            | AuditEvent::GroupMembershipRequested { source, .. }
+            | AuditEvent::GroupMembershipRequestDecided { source, .. }
+            | AuditEvent::SessionAnomaly { source, .. } => match source {
+                AuditSource::Https(ip) | AuditSource::Ldaps(ip) => Some(*ip),
+                AuditSource::Internal => None,
+            },
+            AuditEvent::Oauth2ClientCredentialsGranted { .. }
+            | AuditEvent::Oauth2TokenExchanged { .. }
+            | AuditEvent::ReplicationClockSkew { .. }
+            | AuditEvent::CredentialUpdated { .. }
+            | AuditEvent::AccountLifecycleExpirySet { .. }
+            | AuditEvent::AccountLifecycleArchived { .. }
+            | AuditEvent::ReportGenerated { .. } => None,
+        }
+    }
+}
+
+/// Activity events record the routine use of the server, such as successful authentications
+/// and changes made by users. These are far more frequent than the other audit events, so they
+/// are only produced once activity auditing has been enabled on the idm server.
+pub(crate) type ActivitySender = Option<Sender<AuditEvent>>;
+
+pub(crate) fn send_activity(activity_tx: &ActivitySender, event: AuditEvent) {
+    if let Some(activity_tx) = activity_tx {
+        if activity_tx.send(event).is_err() {
+            error!("Unable to submit audit event to queue");
+        }
+    }
+}
+
+/// An audit event as it is stored and exported. The event fields are flattened into
+/// the record so that each record is a single flat json object, which allows log
+/// pipelines to index these without additional processing.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub version: u32,
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    /// Where the source of the event is located, if a GeoIP database is loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoLocation>,
+}
+
+impl From<AuditEvent> for AuditRecord {
+    fn from(event: AuditEvent) -> Self {
+        AuditRecord {
+            version: AUDIT_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            event,
+            geo: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditEvent, AuditRecord, AuditSource, AUDIT_SCHEMA_VERSION};
+    use crate::idm::geoip::{GeoLocation, SessionAnomaly};
+    use crate::prelude::*;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn test_audit_record_schema() {
+        let event = AuditEvent::AuthenticationDenied {
+            source: AuditSource::Https("127.0.0.1".parse().expect("Invalid ip")),
+            uuid: UUID_ADMIN,
+            spn: "admin@example.com".to_string(),
+            time: OffsetDateTime::UNIX_EPOCH,
+        };
+        let record = AuditRecord::from(event);
+        assert_eq!(record.version, AUDIT_SCHEMA_VERSION);
+        assert_eq!(record.event.name(), "authentication_denied");
+
+        let json = serde_json::to_value(&record).expect("Unable to serialise record");
+        assert_eq!(json["event"], "authentication_denied");
+        assert_eq!(json["source"]["type"], "https");
+        assert_eq!(json["source"]["address"], "127.0.0.1");
+        assert_eq!(json["time"], "1970-01-01T00:00:00Z");
+
+        let decoded: AuditRecord =
+            serde_json::from_value(json).expect("Unable to deserialise record");
+        assert_eq!(decoded, record);
+    }
+
+    #[test]
+    fn test_audit_record_entries_modified() {
+        let event = AuditEvent::EntriesModified {
+            source: AuditSource::Internal,
+            actor: UUID_IDM_ADMIN,
+            targets: [UUID_ADMIN].into(),
+            attrs: [Attribute::DisplayName, Attribute::Mail].into(),
+            time: OffsetDateTime::UNIX_EPOCH,
+        };
+        let record = AuditRecord::from(event);
+
+        let json = serde_json::to_value(&record).expect("Unable to serialise record");
+        assert_eq!(json["event"], "entries_modified");
+        assert_eq!(json["source"]["type"], "internal");
+        assert_eq!(json["actor"], UUID_IDM_ADMIN.to_string());
+        assert_eq!(json["attrs"], serde_json::json!(["displayname", "mail"]));
+
+        let decoded: AuditRecord =
+            serde_json::from_value(json).expect("Unable to deserialise record");
+        assert_eq!(decoded, record);
+    }
+
+    #[test]
+    fn test_audit_record_session_anomaly() {
+        let event = AuditEvent::SessionAnomaly {
+            source: AuditSource::Https("192.0.2.1".parse().expect("Invalid ip")),
+            uuid: UUID_ADMIN,
+            spn: "admin@example.com".to_string(),
+            anomaly: SessionAnomaly::NewCountry {
+                country: "NZ".to_string(),
+            },
+            time: OffsetDateTime::UNIX_EPOCH,
+        };
+        assert_eq!(event.source_ip(), "192.0.2.1".parse().ok());
+
+        let record = AuditRecord {
+            geo: Some(GeoLocation {
+                country: Some("NZ".to_string()),
+                asn: Some(64496),
+                asn_org: None,
+            }),
+            ..AuditRecord::from(event)
+        };
+
+        let json = serde_json::to_value(&record).expect("Unable to serialise record");
+        assert_eq!(json["event"], "session_anomaly");
+        assert_eq!(json["anomaly"]["kind"], "new_country");
+        assert_eq!(json["anomaly"]["country"], "NZ");
+        assert_eq!(
+            json["geo"],
+            serde_json::json!({ "country": "NZ", "asn": 64496 })
+        );
+
+        let decoded: AuditRecord =
+            serde_json::from_value(json).expect("Unable to deserialise record");
+        assert_eq!(decoded, record);
+
+        // Records without a location don't include it.
+        let json = serde_json::to_value(AuditRecord::from(AuditEvent::ReplicationClockSkew {
+            partner: "example".to_string(),
+            skew_ms: 0,
+            tolerance_exceeded: false,
+            time: OffsetDateTime::UNIX_EPOCH,
+        }))
+        .expect("Unable to serialise record");
+        assert!(json.get("geo").is_none());
+    }
 }
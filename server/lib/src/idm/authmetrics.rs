@@ -0,0 +1,142 @@
+//! Counters of how far authentication sessions progress through the login steps. These show
+//! administrators where people abandon or fail to login, and how often each mechanism is used.
+//! Only totals are kept - no account, session or source information is recorded.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use kanidm_proto::v1::AuthMech;
+
+/// The mechanisms that are counted, in the order they are reported.
+pub const AUTH_METRICS_MECHS: [AuthMech; 9] = [
+    AuthMech::Anonymous,
+    AuthMech::Password,
+    AuthMech::PasswordBackupCode,
+    AuthMech::PasswordTotp,
+    AuthMech::PasswordSecurityKey,
+    AuthMech::Passkey,
+    AuthMech::EmailLink,
+    AuthMech::Federated,
+    AuthMech::JwtAssertion,
+];
+
+fn mech_index(mech: &AuthMech) -> usize {
+    match mech {
+        AuthMech::Anonymous => 0,
+        AuthMech::Password => 1,
+        AuthMech::PasswordBackupCode => 2,
+        AuthMech::PasswordTotp => 3,
+        AuthMech::PasswordSecurityKey => 4,
+        AuthMech::Passkey => 5,
+        AuthMech::EmailLink => 6,
+        AuthMech::Federated => 7,
+        AuthMech::JwtAssertion => 8,
+    }
+}
+
+/// A step of an authentication session, once a mechanism has been chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthFunnelStep {
+    /// The mechanism was selected.
+    Chosen,
+    /// A credential was submitted for the first time in the session.
+    Submitted,
+    Succeeded,
+    Denied,
+}
+
+impl AuthFunnelStep {
+    pub const ALL: [AuthFunnelStep; 4] = [
+        AuthFunnelStep::Chosen,
+        AuthFunnelStep::Submitted,
+        AuthFunnelStep::Succeeded,
+        AuthFunnelStep::Denied,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuthFunnelStep::Chosen => "chosen",
+            AuthFunnelStep::Submitted => "submitted",
+            AuthFunnelStep::Succeeded => "succeeded",
+            AuthFunnelStep::Denied => "denied",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct AuthFunnelMetrics {
+    initiated: AtomicU64,
+    steps: [[AtomicU64; AuthFunnelStep::ALL.len()]; AUTH_METRICS_MECHS.len()],
+}
+
+impl AuthFunnelMetrics {
+    pub(crate) fn record_initiated(&self) {
+        self.initiated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record(&self, mech: &AuthMech, step: AuthFunnelStep) {
+        self.steps[mech_index(mech)][step.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> AuthFunnelSnapshot {
+        AuthFunnelSnapshot {
+            initiated: self.initiated.load(Ordering::Relaxed),
+            steps: self
+                .steps
+                .each_ref()
+                .map(|mech| mech.each_ref().map(|c| c.load(Ordering::Relaxed))),
+        }
+    }
+}
+
+/// The totals of the authentication funnel at a point in time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthFunnelSnapshot {
+    /// The number of authentication sessions that were started.
+    pub initiated: u64,
+    steps: [[u64; AuthFunnelStep::ALL.len()]; AUTH_METRICS_MECHS.len()],
+}
+
+impl AuthFunnelSnapshot {
+    /// The number of sessions that reached a step with a mechanism.
+    pub fn get(&self, mech: &AuthMech, step: AuthFunnelStep) -> u64 {
+        self.steps[mech_index(mech)][step.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthFunnelMetrics, AuthFunnelStep};
+    use kanidm_proto::v1::AuthMech;
+
+    #[test]
+    fn test_auth_funnel_metrics() {
+        let metrics = AuthFunnelMetrics::default();
+
+        metrics.record_initiated();
+        metrics.record_initiated();
+        metrics.record(&AuthMech::Passkey, AuthFunnelStep::Chosen);
+        metrics.record(&AuthMech::Passkey, AuthFunnelStep::Submitted);
+        metrics.record(&AuthMech::Passkey, AuthFunnelStep::Succeeded);
+        metrics.record(&AuthMech::Password, AuthFunnelStep::Chosen);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.initiated, 2);
+        assert_eq!(
+            snapshot.get(&AuthMech::Passkey, AuthFunnelStep::Succeeded),
+            1
+        );
+        assert_eq!(snapshot.get(&AuthMech::Password, AuthFunnelStep::Chosen), 1);
+        assert_eq!(
+            snapshot.get(&AuthMech::Password, AuthFunnelStep::Submitted),
+            0
+        );
+        assert_eq!(
+            snapshot.get(&AuthMech::PasswordTotp, AuthFunnelStep::Chosen),
+            0
+        );
+    }
+}
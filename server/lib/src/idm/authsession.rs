@@ -6,17 +6,18 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Duration;
 
+use compact_jwt::compact::JwkKeySet;
 use compact_jwt::Jws;
 use hashbrown::HashSet;
-use kanidm_proto::internal::UserAuthToken;
+use kanidm_proto::internal::{UatPurpose, UserAuthToken};
 use kanidm_proto::v1::{AuthAllowed, AuthCredential, AuthIssueSession, AuthMech};
 use nonempty::NonEmpty;
 use tokio::sync::mpsc::UnboundedSender as Sender;
 use uuid::Uuid;
 use webauthn_rs::prelude::{
     AttestationCaList, AttestedPasskey as AttestedPasskeyV4, AttestedPasskeyAuthentication,
-    CredentialID, Passkey as PasskeyV4, PasskeyAuthentication, RequestChallengeResponse,
-    SecurityKeyAuthentication, Webauthn,
+    CredentialID, DiscoverableAuthentication, DiscoverableKey, Passkey as PasskeyV4,
+    PasskeyAuthentication, RequestChallengeResponse, SecurityKeyAuthentication, Webauthn,
 };
 
 use crate::credential::totp::Totp;
@@ -26,10 +27,21 @@ use crate::idm::audit::AuditEvent;
 use crate::idm::delayed::{
     AuthSessionRecord, BackupCodeRemoval, DelayedAction, PasswordUpgrade, WebauthnCounterIncrement,
 };
-use crate::idm::AuthState;
+use crate::idm::emaillink::{email_link_issue, EmailLinkMessage};
+use crate::idm::jwtassertion::jwt_assertion_verify;
+use crate::idm::oidcupstream::{
+    OidcUpstream, OidcUpstreamExchange, OidcUpstreamRequest, OIDC_UPSTREAM_CALLBACK_PATH,
+};
+use crate::idm::passwordexpiry::password_expired_token_issue;
+use crate::idm::session_binding::session_key_for;
+use crate::idm::{AuthDeniedKind, AuthState};
 use crate::prelude::*;
 use crate::server::keys::KeyObject;
-use crate::value::{AuthType, Session, SessionState};
+use crate::utils::password_from_random;
+use crate::value::{
+    AuthType, CredentialFactor, CredentialType as PolicyCredentialType, Session, SessionState,
+};
+use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
 use super::accountpolicy::ResolvedAccountPolicy;
@@ -44,10 +56,47 @@ const BAD_TOTP_MSG: &str = "incorrect totp";
 const BAD_WEBAUTHN_MSG: &str = "invalid webauthn authentication";
 const BAD_ACCOUNT_POLICY: &str = "the credential no longer meets account policy requirements";
 const BAD_BACKUPCODE_MSG: &str = "invalid backup code";
+const BAD_EMAIL_LINK_MSG: &str = "invalid or expired email link";
+const BAD_FEDERATED_MSG: &str = "invalid upstream identity provider login";
+const BAD_JWT_ASSERTION_MSG: &str = "invalid jwt assertion";
 const BAD_AUTH_TYPE_MSG: &str = "invalid authentication method in this context";
 const BAD_CREDENTIALS: &str = "invalid credential message";
 const ACCOUNT_EXPIRED: &str = "account expired";
+const PASSWORD_EXPIRED_MSG: &str = "password expired";
+const UNTRUSTED_NETWORK: &str = "authentication is not permitted from this network";
+const UNUSUAL_LOCATION: &str =
+    "multi-factor authentication is required to sign in from this location";
 const PW_BADLIST_MSG: &str = "password is in badlist";
+const ACCOUNT_SOFTLOCKED: &str = "Account is temporarily locked";
+const ACCOUNT_LOCKED_OUT: &str = "Account is locked due to repeated authentication failures";
+pub(crate) const CREDENTIAL_SOFTLOCKED: &str = "Credential is temporarily locked";
+
+impl AuthDeniedKind {
+    /// Categorise the reason that an authentication was denied.
+    pub fn from_reason(reason: &str) -> Self {
+        if reason.starts_with(ACCOUNT_LOCKED_OUT)
+            || reason == ACCOUNT_SOFTLOCKED
+            || reason == CREDENTIAL_SOFTLOCKED
+        {
+            AuthDeniedKind::AccountLocked
+        } else if reason == ACCOUNT_EXPIRED {
+            AuthDeniedKind::AccountExpired
+        } else if [
+            BAD_ACCOUNT_POLICY,
+            BAD_AUTH_TYPE_MSG,
+            UNTRUSTED_NETWORK,
+            UNUSUAL_LOCATION,
+            PW_BADLIST_MSG,
+            PASSWORD_EXPIRED_MSG,
+        ]
+        .contains(&reason)
+        {
+            AuthDeniedKind::PolicyDenied
+        } else {
+            AuthDeniedKind::CredentialsIncorrect
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 enum AuthIntent {
@@ -81,6 +130,8 @@ struct CredTotp {
     pw: Password,
     pw_state: CredVerifyState,
     totp: BTreeMap<String, Totp>,
+    // The label of the totp that was verified.
+    totp_used: Option<String>,
     mfa_state: CredVerifyState,
 }
 
@@ -119,6 +170,79 @@ struct CredAttestedPasskey {
     state: CredVerifyState,
 }
 
+#[derive(Clone, Debug)]
+/// The state of a passkey that was selected by the user before the account was known.
+struct CredDiscoverablePasskey {
+    chal: RequestChallengeResponse,
+    wan_state: DiscoverableAuthentication,
+    keys: Vec<DiscoverableKey>,
+    state: CredVerifyState,
+}
+
+#[derive(Clone, Debug)]
+/// A passkey challenge that is issued before the account is known, such as to the username
+/// autofill of the login page. The account is identified from the user handle of the passkey
+/// that is selected.
+pub(crate) struct DiscoverableChallenge {
+    chal: RequestChallengeResponse,
+    wan_state: DiscoverableAuthentication,
+}
+
+impl DiscoverableChallenge {
+    pub(crate) fn new(webauthn: &Webauthn) -> Result<Self, OperationError> {
+        webauthn
+            .start_discoverable_authentication()
+            .map(|(chal, wan_state)| DiscoverableChallenge { chal, wan_state })
+            .map_err(|e| {
+                security_info!(
+                    ?e,
+                    "Unable to create discoverable passkey webauthn authentication challenge"
+                );
+                OperationError::InvalidState
+            })
+    }
+
+    pub(crate) fn challenge(&self) -> &RequestChallengeResponse {
+        &self.chal
+    }
+}
+
+#[derive(Clone, Debug)]
+/// The state of an email link during authentication.
+struct CredEmailLink {
+    mail: String,
+    // The token that was sent and when it expires. This is set once the link is sent.
+    sent: Option<(String, Duration)>,
+    state: CredVerifyState,
+}
+
+#[derive(Clone, Debug)]
+/// The state of a login with an upstream identity provider during authentication.
+struct CredFederated {
+    requests: Vec<OidcUpstreamRequest>,
+    redirect_uri: Url,
+    mails: Vec<String>,
+    // The request that the provider returned with.
+    selected: Option<usize>,
+    // The result of verifying the id token from the provider.
+    verified: Option<Result<(), &'static str>>,
+    state: CredVerifyState,
+}
+
+#[derive(Clone, Debug)]
+/// The state of a jwt assertion during authentication.
+struct CredJwtAssertion {
+    keys: BTreeMap<String, Vec<u8>>,
+    // The names of the account that the assertion may be issued by.
+    subjects: Vec<String>,
+    audience: Url,
+    // Issued to the client, and must be contained in the assertion.
+    nonce: String,
+    // The label of the key that signed the assertion.
+    key_used: Option<String>,
+    state: CredVerifyState,
+}
+
 /// The current active handler for this authentication session. This is determined from what credentials
 /// are possible from the account, and what the user selected as the preferred authentication
 /// mechanism.
@@ -155,6 +279,24 @@ enum CredHandler {
         // AP does `PartialEq` on cred_id
         creds: BTreeMap<AttestedPasskeyV4, Uuid>,
     },
+    DiscoverablePasskey {
+        c_wan: CredDiscoverablePasskey,
+        cred_ids: BTreeMap<CredentialID, Uuid>,
+        // Only attested passkeys were offered, as the account policy requires them.
+        attested: bool,
+    },
+    EmailLink {
+        link: CredEmailLink,
+        cred_id: Uuid,
+    },
+    Federated {
+        federated: CredFederated,
+        cred_id: Uuid,
+    },
+    JwtAssertion {
+        assertion: CredJwtAssertion,
+        cred_id: Uuid,
+    },
 }
 
 impl CredHandler {
@@ -227,6 +369,58 @@ impl CredHandler {
             .ok()
     }
 
+    /// Build the handler for a passkey the user selected from a discoverable challenge. When
+    /// the account policy requires attestation, only the attested passkeys that still meet
+    /// the policy are accepted.
+    fn build_from_discoverable_passkey(
+        account: &Account,
+        att_ca_list: Option<&AttestationCaList>,
+        challenge: DiscoverableChallenge,
+    ) -> Option<Self> {
+        let passkeys: Vec<(Uuid, PasskeyV4)> = if let Some(att_ca_list) = att_ca_list {
+            account
+                .attested_passkeys
+                .iter()
+                .filter(|(_, (_, apk))| apk.verify_attestation(att_ca_list).is_ok())
+                .map(|(u, (_, apk))| (*u, apk.into()))
+                .collect()
+        } else {
+            account
+                .passkeys
+                .iter()
+                .map(|(u, (_, pk))| (*u, pk.clone()))
+                .chain(
+                    account
+                        .attested_passkeys
+                        .iter()
+                        .map(|(u, (_, apk))| (*u, apk.into())),
+                )
+                .collect()
+        };
+
+        if passkeys.is_empty() {
+            debug!("Account does not have any usable passkeys");
+            return None;
+        };
+
+        let cred_ids = passkeys
+            .iter()
+            .map(|(uuid, pk)| (pk.cred_id().clone(), *uuid))
+            .collect();
+        let keys = passkeys.iter().map(|(_, pk)| pk.into()).collect();
+
+        Some(CredHandler::DiscoverablePasskey {
+            c_wan: CredDiscoverablePasskey {
+                chal: challenge.chal,
+                wan_state: challenge.wan_state,
+                keys,
+                state: CredVerifyState::Init,
+            },
+            cred_ids,
+            attested: att_ca_list.is_some(),
+        })
+    }
+
     fn build_from_set_attested_pk(
         wan: &BTreeMap<Uuid, (String, AttestedPasskeyV4)>,
         att_ca_list: &AttestationCaList,
@@ -304,6 +498,7 @@ impl CredHandler {
                             .iter()
                             .map(|(l, t)| (l.clone(), t.clone()))
                             .collect(),
+                        totp_used: None,
                         mfa_state: CredVerifyState::Init,
                     };
 
@@ -368,6 +563,87 @@ impl CredHandler {
         }
     }
 
+    /// Email links are only offered when mail can be sent, the account policy allows them
+    /// and the account has a mail address.
+    fn build_from_email_link(
+        account: &Account,
+        account_policy: &ResolvedAccountPolicy,
+        email_link_tx: Option<&Sender<EmailLinkMessage>>,
+    ) -> Option<Self> {
+        if email_link_tx.is_none() || !account_policy.allow_email_link() {
+            return None;
+        }
+
+        account
+            .mail_primary
+            .as_ref()
+            .map(|mail| CredHandler::EmailLink {
+                link: CredEmailLink {
+                    mail: mail.clone(),
+                    sent: None,
+                    state: CredVerifyState::Init,
+                },
+                cred_id: account.uuid,
+            })
+    }
+
+    /// Upstream identity providers are only offered when at least one is configured, and the
+    /// account has a mail address that the provider can identify the person by.
+    fn build_from_federated(
+        account: &Account,
+        oidc_upstreams: &[OidcUpstream],
+        webauthn: &Webauthn,
+    ) -> Option<Self> {
+        if oidc_upstreams.is_empty() || account.mail.is_empty() {
+            return None;
+        }
+
+        let mut redirect_uri = webauthn.get_allowed_origins().first()?.clone();
+        redirect_uri.set_path(OIDC_UPSTREAM_CALLBACK_PATH);
+
+        Some(CredHandler::Federated {
+            federated: CredFederated {
+                requests: oidc_upstreams
+                    .iter()
+                    .cloned()
+                    .map(OidcUpstreamRequest::new)
+                    .collect(),
+                redirect_uri,
+                mails: account.mail.clone(),
+                selected: None,
+                verified: None,
+                state: CredVerifyState::Init,
+            },
+            cred_id: account.uuid,
+        })
+    }
+
+    /// Jwt assertions are offered when the account has registered public keys. Each session
+    /// has a new nonce, so an assertion can only ever be used for a single authentication.
+    fn build_from_jwt_assertion(account: &Account, webauthn: &Webauthn) -> Option<Self> {
+        if account.jwt_public_keys.is_empty() {
+            return None;
+        }
+
+        let audience = webauthn.get_allowed_origins().first()?.clone();
+
+        Some(CredHandler::JwtAssertion {
+            assertion: CredJwtAssertion {
+                keys: account.jwt_public_keys.clone(),
+                subjects: vec![
+                    account.name.clone(),
+                    account.spn.clone(),
+                    account.uuid.as_hyphenated().to_string(),
+                ],
+                audience,
+                nonce: password_from_random(),
+                key_used: None,
+                state: CredVerifyState::Init,
+            },
+            cred_id: account.uuid,
+        })
+    }
+
     fn build_from_password_only(cred: &Credential) -> Option<Self> {
         match &cred.type_ {
             CredentialType::Password(pw) => Some(CredHandler::Password {
@@ -493,12 +769,13 @@ impl CredHandler {
                             .totp
                             .iter()
                             .find(|(_, t)| t.verify(*totp_chal, ts))
-                            .map(|(l, _)| l)
+                            .map(|(l, _)| l.clone())
                         {
                             pw_mfa.mfa_state = CredVerifyState::Success;
                             security_info!(
                                 "Handler::PasswordMfa -> Result::Continue - TOTP ({}) OK, password -", label
                             );
+                            pw_mfa.totp_used = Some(label);
                             CredState::Continue(Box::new(NonEmpty {
                                 head: AuthAllowed::Password,
                                 tail: Vec::with_capacity(0),
@@ -894,6 +1171,202 @@ impl CredHandler {
         }
     }
 
+    /// Validate a passkey that was selected from a discoverable challenge
+    pub fn validate_discoverable_passkey(
+        cred: &AuthCredential,
+        cred_ids: &BTreeMap<CredentialID, Uuid>,
+        attested: bool,
+        wan_cred: &mut CredDiscoverablePasskey,
+        webauthn: &Webauthn,
+        who: Uuid,
+        async_tx: &Sender<DelayedAction>,
+    ) -> CredState {
+        if wan_cred.state != CredVerifyState::Init {
+            security_error!("Handler::Webauthn -> Result::Denied - Internal State Already Fail");
+            return CredState::Denied(BAD_WEBAUTHN_MSG);
+        }
+
+        match cred {
+            AuthCredential::Passkey(resp) => {
+                match webauthn.finish_discoverable_authentication(
+                    resp,
+                    wan_cred.wan_state.clone(),
+                    &wan_cred.keys,
+                ) {
+                    Ok(auth_result) => {
+                        if let Some(cred_id) = cred_ids.get(auth_result.cred_id()).copied() {
+                            wan_cred.state = CredVerifyState::Success;
+                            if auth_result.needs_update() {
+                                if let Err(_e) =
+                                    async_tx.send(DelayedAction::WebauthnCounterIncrement(
+                                        WebauthnCounterIncrement {
+                                            target_uuid: who,
+                                            auth_result,
+                                        },
+                                    ))
+                                {
+                                    admin_warn!("unable to queue delayed webauthn property update, continuing ... ");
+                                };
+                            };
+
+                            CredState::Success {
+                                auth_type: if attested {
+                                    AuthType::AttestedPasskey
+                                } else {
+                                    AuthType::Passkey
+                                },
+                                cred_id,
+                            }
+                        } else {
+                            wan_cred.state = CredVerifyState::Fail;
+                            security_error!("Handler::Webauthn -> Result::Denied - webauthn credential id not found");
+                            CredState::Denied(BAD_WEBAUTHN_MSG)
+                        }
+                    }
+                    Err(e) => {
+                        wan_cred.state = CredVerifyState::Fail;
+                        security_error!(?e, "Handler::Webauthn -> Result::Denied - webauthn error");
+                        CredState::Denied(BAD_WEBAUTHN_MSG)
+                    }
+                }
+            }
+            _ => {
+                security_error!(
+                    "Handler::Webauthn -> Result::Denied - invalid cred type for handler"
+                );
+                CredState::Denied(BAD_AUTH_TYPE_MSG)
+            }
+        }
+    }
+
+    /// Validate the token from an email link. The link may only be presented once.
+    fn validate_email_link(
+        cred: &AuthCredential,
+        cred_id: Uuid,
+        ts: Duration,
+        link: &mut CredEmailLink,
+    ) -> CredState {
+        if link.state != CredVerifyState::Init {
+            security_error!("Handler::EmailLink -> Result::Denied - Internal State Already Fail");
+            return CredState::Denied(BAD_EMAIL_LINK_MSG);
+        }
+
+        match (cred, &link.sent) {
+            (AuthCredential::EmailLink(token), Some((sent, expiry)))
+                if token == sent && ts < *expiry =>
+            {
+                link.state = CredVerifyState::Success;
+                security_info!("Handler::EmailLink -> Result::Success");
+                CredState::Success {
+                    auth_type: AuthType::EmailLink,
+                    cred_id,
+                }
+            }
+            (AuthCredential::EmailLink(_), _) => {
+                link.state = CredVerifyState::Fail;
+                security_error!("Handler::EmailLink -> Result::Denied - invalid or expired link");
+                CredState::Denied(BAD_EMAIL_LINK_MSG)
+            }
+            _ => {
+                security_error!(
+                    "Handler::EmailLink -> Result::Denied - invalid cred type for handler"
+                );
+                CredState::Denied(BAD_AUTH_TYPE_MSG)
+            }
+        }
+    }
+
+    /// Validate the state the provider returned with. The id token must already have been
+    /// verified for the same request.
+    fn validate_federated(
+        cred: &AuthCredential,
+        cred_id: Uuid,
+        federated: &mut CredFederated,
+    ) -> CredState {
+        if federated.state != CredVerifyState::Init {
+            security_error!("Handler::Federated -> Result::Denied - Internal State Already Fail");
+            return CredState::Denied(BAD_FEDERATED_MSG);
+        }
+
+        let AuthCredential::Federated(state) = cred else {
+            security_error!("Handler::Federated -> Result::Denied - invalid cred type for handler");
+            return CredState::Denied(BAD_AUTH_TYPE_MSG);
+        };
+
+        let selected = federated
+            .selected
+            .and_then(|idx| federated.requests.get(idx))
+            .filter(|request| request.state() == state);
+
+        match (selected, federated.verified) {
+            (Some(_), Some(Ok(()))) => {
+                federated.state = CredVerifyState::Success;
+                security_info!("Handler::Federated -> Result::Success");
+                CredState::Success {
+                    auth_type: AuthType::Federated,
+                    cred_id,
+                }
+            }
+            (_, Some(Err(reason))) => {
+                federated.state = CredVerifyState::Fail;
+                security_error!(%reason, "Handler::Federated -> Result::Denied");
+                CredState::Denied(BAD_FEDERATED_MSG)
+            }
+            (_, _) => {
+                federated.state = CredVerifyState::Fail;
+                security_error!("Handler::Federated -> Result::Denied - login was not verified");
+                CredState::Denied(BAD_FEDERATED_MSG)
+            }
+        }
+    }
+
+    /// Validate a jwt assertion, which must be signed by a key of the account and contain the
+    /// nonce of this session.
+    fn validate_jwt_assertion(
+        cred: &AuthCredential,
+        cred_id: Uuid,
+        ts: Duration,
+        assertion: &mut CredJwtAssertion,
+    ) -> CredState {
+        if assertion.state != CredVerifyState::Init {
+            security_error!(
+                "Handler::JwtAssertion -> Result::Denied - Internal State Already Fail"
+            );
+            return CredState::Denied(BAD_JWT_ASSERTION_MSG);
+        }
+
+        let AuthCredential::JwtAssertion(jwt) = cred else {
+            security_error!(
+                "Handler::JwtAssertion -> Result::Denied - invalid cred type for handler"
+            );
+            return CredState::Denied(BAD_AUTH_TYPE_MSG);
+        };
+
+        match jwt_assertion_verify(
+            jwt,
+            &assertion.keys,
+            &assertion.subjects,
+            &assertion.audience,
+            &assertion.nonce,
+            ts,
+        ) {
+            Ok(label) => {
+                assertion.state = CredVerifyState::Success;
+                security_info!(%label, "Handler::JwtAssertion -> Result::Success");
+                assertion.key_used = Some(label);
+                CredState::Success {
+                    auth_type: AuthType::JwtAssertion,
+                    cred_id,
+                }
+            }
+            Err(reason) => {
+                assertion.state = CredVerifyState::Fail;
+                security_error!(%reason, "Handler::JwtAssertion -> Result::Denied");
+                CredState::Denied(BAD_JWT_ASSERTION_MSG)
+            }
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     /// Given the current handler, proceed to authenticate the attempted credential step.
     pub fn validate(
@@ -972,6 +1445,25 @@ impl CredHandler {
                 async_tx,
                 att_ca_list,
             ),
+            CredHandler::DiscoverablePasskey {
+                ref mut c_wan,
+                cred_ids,
+                attested,
+            } => Self::validate_discoverable_passkey(
+                cred, cred_ids, *attested, c_wan, webauthn, who, async_tx,
+            ),
+            CredHandler::EmailLink {
+                ref mut link,
+                cred_id,
+            } => Self::validate_email_link(cred, *cred_id, ts, link),
+            CredHandler::Federated {
+                ref mut federated,
+                cred_id,
+            } => Self::validate_federated(cred, *cred_id, federated),
+            CredHandler::JwtAssertion {
+                ref mut assertion,
+                cred_id,
+            } => Self::validate_jwt_assertion(cred, *cred_id, ts, assertion),
         }
     }
 
@@ -991,6 +1483,22 @@ impl CredHandler {
             CredHandler::AttestedPasskey { c_wan, .. } => {
                 vec![AuthAllowed::Passkey(c_wan.chal.clone())]
             }
+            CredHandler::DiscoverablePasskey { c_wan, .. } => {
+                vec![AuthAllowed::Passkey(c_wan.chal.clone())]
+            }
+            CredHandler::EmailLink { .. } => vec![AuthAllowed::EmailLink],
+            CredHandler::Federated { federated, .. } => {
+                vec![AuthAllowed::Federated(
+                    federated
+                        .requests
+                        .iter()
+                        .map(|request| request.provider(&federated.redirect_uri))
+                        .collect(),
+                )]
+            }
+            CredHandler::JwtAssertion { assertion, .. } => {
+                vec![AuthAllowed::JwtAssertion(assertion.nonce.clone())]
+            }
         }
     }
 
@@ -1003,7 +1511,11 @@ impl CredHandler {
             | (CredHandler::PasswordBackupCode { .. }, AuthMech::PasswordBackupCode)
             | (CredHandler::PasswordSecurityKey { .. }, AuthMech::PasswordSecurityKey)
             | (CredHandler::Passkey { .. }, AuthMech::Passkey)
-            | (CredHandler::AttestedPasskey { .. }, AuthMech::Passkey) => true,
+            | (CredHandler::AttestedPasskey { .. }, AuthMech::Passkey)
+            | (CredHandler::DiscoverablePasskey { .. }, AuthMech::Passkey)
+            | (CredHandler::EmailLink { .. }, AuthMech::EmailLink)
+            | (CredHandler::Federated { .. }, AuthMech::Federated)
+            | (CredHandler::JwtAssertion { .. }, AuthMech::JwtAssertion) => true,
             (_, _) => false,
         }
     }
@@ -1017,6 +1529,65 @@ impl CredHandler {
             CredHandler::PasswordSecurityKey { .. } => AuthMech::PasswordSecurityKey,
             CredHandler::Passkey { .. } => AuthMech::Passkey,
             CredHandler::AttestedPasskey { .. } => AuthMech::Passkey,
+            CredHandler::DiscoverablePasskey { .. } => AuthMech::Passkey,
+            CredHandler::EmailLink { .. } => AuthMech::EmailLink,
+            CredHandler::Federated { .. } => AuthMech::Federated,
+            CredHandler::JwtAssertion { .. } => AuthMech::JwtAssertion,
+        }
+    }
+
+    /// The factors of the credential that were used by a successful authentication.
+    fn used_factors(&self) -> Vec<CredentialFactor> {
+        match self {
+            CredHandler::Anonymous { .. } => Vec::with_capacity(0),
+            CredHandler::Password { .. } => vec![CredentialFactor::Password],
+            CredHandler::PasswordTotp { cmfa, .. } => {
+                let mut factors = vec![CredentialFactor::Password];
+                if let Some(label) = &cmfa.totp_used {
+                    factors.push(CredentialFactor::Totp(label.clone()));
+                }
+                factors
+            }
+            CredHandler::PasswordBackupCode { .. } => {
+                vec![CredentialFactor::Password, CredentialFactor::BackupCode]
+            }
+            CredHandler::PasswordSecurityKey { .. } => {
+                vec![CredentialFactor::Password, CredentialFactor::SecurityKey]
+            }
+            CredHandler::Passkey { .. }
+            | CredHandler::AttestedPasskey { .. }
+            | CredHandler::DiscoverablePasskey { .. } => vec![CredentialFactor::Passkey],
+            CredHandler::EmailLink { .. } => vec![CredentialFactor::EmailLink],
+            CredHandler::Federated { .. } => vec![CredentialFactor::Federated],
+            CredHandler::JwtAssertion { assertion, .. } => assertion
+                .key_used
+                .iter()
+                .cloned()
+                .map(CredentialFactor::JwtAssertion)
+                .collect(),
+        }
+    }
+
+    /// The strength of the credential this handler will authenticate.
+    fn credential_type(&self) -> PolicyCredentialType {
+        match self {
+            CredHandler::Anonymous { .. }
+            | CredHandler::Password { .. }
+            | CredHandler::EmailLink { .. }
+            | CredHandler::Federated { .. }
+            | CredHandler::JwtAssertion { .. } => PolicyCredentialType::Any,
+            CredHandler::PasswordTotp { .. }
+            | CredHandler::PasswordBackupCode { .. }
+            | CredHandler::PasswordSecurityKey { .. } => PolicyCredentialType::Mfa,
+            CredHandler::Passkey { .. } => PolicyCredentialType::Passkey,
+            CredHandler::AttestedPasskey { .. } => PolicyCredentialType::AttestedPasskey,
+            CredHandler::DiscoverablePasskey { attested, .. } => {
+                if *attested {
+                    PolicyCredentialType::AttestedPasskey
+                } else {
+                    PolicyCredentialType::Passkey
+                }
+            }
         }
     }
 }
@@ -1056,6 +1627,13 @@ pub(crate) struct AuthSessionData<'a> {
     pub(crate) webauthn: &'a Webauthn,
     pub(crate) ct: Duration,
     pub(crate) client_auth_info: ClientAuthInfo,
+    // Where email links are sent for delivery. If `None`, mail can't be sent.
+    pub(crate) email_link_tx: Option<Sender<EmailLinkMessage>>,
+    // The upstream identity providers that may be offered.
+    pub(crate) oidc_upstreams: Vec<OidcUpstream>,
+    // The sign in is from an unusual location, so at least multi-factor credentials are
+    // required.
+    pub(crate) unusual_location: bool,
 }
 
 #[derive(Clone)]
@@ -1084,8 +1662,21 @@ pub(crate) struct AuthSession {
     // Where did the event come from?
     source: Source,
 
+    // What client did the event come from? This is recorded on the session that is issued.
+    user_agent: Option<String>,
+
+    // The key held by the browser that the issued session is bound to, if any.
+    session_key: Option<String>,
+
     // The cryptographic provider to encrypt or sign anything in this operation.
     key_object: Arc<KeyObject>,
+
+    // Has a credential been presented yet? Multi factor mechanisms take several steps, and the
+    // auth funnel metrics only count the first.
+    credentials_submitted: bool,
+
+    // Where email links are sent for delivery.
+    email_link_tx: Option<Sender<EmailLinkMessage>>,
 }
 
 impl AuthSession {
@@ -1097,10 +1688,30 @@ impl AuthSession {
         privileged: bool,
         key_object: Arc<KeyObject>,
     ) -> (Option<Self>, AuthState) {
+        // Where the client is connecting from may require stronger credentials.
+        let network_cred_type_min = asd
+            .account_policy
+            .network_credential_policy(&asd.client_auth_info.source);
+        // As does signing in from somewhere unusual for the account.
+        let cred_type_min = if asd.unusual_location {
+            network_cred_type_min.max(PolicyCredentialType::Mfa)
+        } else {
+            network_cred_type_min
+        };
+
         // During this setup, determine the credential handler that we'll be using
         // for this session. This is currently based on presentation of an application
         // id.
-        let state = if asd.account.is_within_valid_time(asd.ct) {
+        let state = if !asd.account.is_within_valid_time(asd.ct) {
+            security_info!("account expired");
+            AuthSessionState::Denied(ACCOUNT_EXPIRED)
+        } else if network_cred_type_min == PolicyCredentialType::Invalid {
+            security_info!(
+                source = ?asd.client_auth_info.source,
+                "account policy denies authentication from this network"
+            );
+            AuthSessionState::Denied(UNTRUSTED_NETWORK)
+        } else {
             // We want the primary handler - this is where we make a decision
             // based on the anonymous ... in theory this could be cleaner
             // and interact with the account more?
@@ -1175,16 +1786,51 @@ impl AuthSession {
                     }
                 };
 
+                if let Some(ch) = CredHandler::build_from_email_link(
+                    &asd.account,
+                    &asd.account_policy,
+                    asd.email_link_tx.as_ref(),
+                ) {
+                    handlers.push(ch);
+                }
+
+                if let Some(ch) = CredHandler::build_from_federated(
+                    &asd.account,
+                    &asd.oidc_upstreams,
+                    asd.webauthn,
+                ) {
+                    handlers.push(ch);
+                }
+
+                if let Some(ch) = CredHandler::build_from_jwt_assertion(&asd.account, asd.webauthn)
+                {
+                    handlers.push(ch);
+                }
+
+                let has_handlers = !handlers.is_empty();
+                handlers.retain(|ch| ch.credential_type() >= cred_type_min);
+
                 if let Some(non_empty_handlers) = NonEmpty::collect(handlers) {
                     AuthSessionState::Init(non_empty_handlers)
+                } else if has_handlers && cred_type_min > network_cred_type_min {
+                    security_info!(
+                        source = ?asd.client_auth_info.source,
+                        ?cred_type_min,
+                        "account has no credentials that are permitted from this location"
+                    );
+                    AuthSessionState::Denied(UNUSUAL_LOCATION)
+                } else if has_handlers {
+                    security_info!(
+                        source = ?asd.client_auth_info.source,
+                        ?network_cred_type_min,
+                        "account has no credentials that are permitted from this network"
+                    );
+                    AuthSessionState::Denied(UNTRUSTED_NETWORK)
                 } else {
                     security_info!("account has no available credentials");
                     AuthSessionState::Denied("invalid credential state")
                 }
             }
-        } else {
-            security_info!("account expired");
-            AuthSessionState::Denied(ACCOUNT_EXPIRED)
         };
 
         // if credhandler == deny, finish = true.
@@ -1199,8 +1845,12 @@ impl AuthSession {
                 state,
                 issue: asd.issue,
                 intent: AuthIntent::InitialAuth { privileged },
+                session_key: session_key_for(&asd.client_auth_info, asd.ct),
                 source: asd.client_auth_info.source,
+                user_agent: asd.client_auth_info.user_agent,
                 key_object,
+                credentials_submitted: false,
+                email_link_tx: asd.email_link_tx,
             };
             // Get the set of mechanisms that can proceed. This is tied
             // to the session so that it can mutate state and have progression
@@ -1213,6 +1863,77 @@ impl AuthSession {
         }
     }
 
+    /// Build a new auth session for a passkey that the user selected from a discoverable
+    /// challenge. The account was identified from the user handle of the passkey, so the
+    /// passkey handler is preselected and the challenge is the one already sent to the client.
+    pub(crate) fn new_discoverable(
+        asd: AuthSessionData<'_>,
+        challenge: DiscoverableChallenge,
+        key_object: Arc<KeyObject>,
+    ) -> (Option<Self>, AuthState) {
+        let network_cred_type_min = asd
+            .account_policy
+            .network_credential_policy(&asd.client_auth_info.source);
+
+        let state = if !asd.account.is_within_valid_time(asd.ct) {
+            security_info!("account expired");
+            AuthSessionState::Denied(ACCOUNT_EXPIRED)
+        } else if network_cred_type_min == PolicyCredentialType::Invalid {
+            security_info!(
+                source = ?asd.client_auth_info.source,
+                "account policy denies authentication from this network"
+            );
+            AuthSessionState::Denied(UNTRUSTED_NETWORK)
+        } else {
+            match CredHandler::build_from_discoverable_passkey(
+                &asd.account,
+                asd.account_policy.webauthn_attestation_ca_list(),
+                challenge,
+            ) {
+                Some(handler) if handler.credential_type() >= network_cred_type_min => {
+                    AuthSessionState::InProgress(handler)
+                }
+                Some(_) => {
+                    security_info!(
+                        source = ?asd.client_auth_info.source,
+                        ?network_cred_type_min,
+                        "account has no credentials that are permitted from this network"
+                    );
+                    AuthSessionState::Denied(UNTRUSTED_NETWORK)
+                }
+                None => {
+                    security_info!("account has no passkeys that meet the account policy");
+                    AuthSessionState::Denied(BAD_CREDENTIALS)
+                }
+            }
+        };
+
+        let allowed = match &state {
+            AuthSessionState::InProgress(handler) => handler.next_auth_allowed(),
+            _ => Vec::with_capacity(0),
+        };
+
+        if let Some(reason) = state.is_denied() {
+            (None, AuthState::Denied(reason.to_string()))
+        } else {
+            let auth_session = AuthSession {
+                account: asd.account,
+                account_policy: asd.account_policy,
+                state,
+                issue: asd.issue,
+                intent: AuthIntent::InitialAuth { privileged: false },
+                session_key: session_key_for(&asd.client_auth_info, asd.ct),
+                source: asd.client_auth_info.source,
+                user_agent: asd.client_auth_info.user_agent,
+                key_object,
+                credentials_submitted: false,
+                email_link_tx: asd.email_link_tx,
+            };
+
+            (Some(auth_session), AuthState::Continue(allowed))
+        }
+    }
+
     /// Build a new auth session which has been preconfigured for re-authentication.
     /// This differs from [`AuthSession::new`] as we preselect the credential that
     /// will be used in this operation based on the credential id that was used in the
@@ -1228,6 +1949,7 @@ impl AuthSession {
         enum State {
             Expired,
             NoMatchingCred,
+            UntrustedNetwork,
             Proceed(CredHandler),
         }
 
@@ -1315,15 +2037,27 @@ impl AuthSession {
                         }
                     }
                 }
-                AuthType::Anonymous => {}
+                // Email links and jwt assertions are single use, and upstream logins can't be
+                // preselected, so none can be used to reauthenticate.
+                AuthType::Anonymous
+                | AuthType::EmailLink
+                | AuthType::Federated
+                | AuthType::JwtAssertion
+                | AuthType::Impersonation => {}
             }
 
             // Did anything get set-up?
 
-            if let Some(cred_handler) = cred_handler {
-                State::Proceed(cred_handler)
-            } else {
-                State::NoMatchingCred
+            let network_cred_type_min = asd
+                .account_policy
+                .network_credential_policy(&asd.client_auth_info.source);
+
+            match cred_handler {
+                Some(cred_handler) if cred_handler.credential_type() >= network_cred_type_min => {
+                    State::Proceed(cred_handler)
+                }
+                Some(_) => State::UntrustedNetwork,
+                None => State::NoMatchingCred,
             }
         } else {
             State::Expired
@@ -1352,8 +2086,12 @@ impl AuthSession {
                         session_id,
                         session_expiry,
                     },
+                    session_key: session_key_for(&asd.client_auth_info, asd.ct),
                     source: asd.client_auth_info.source,
+                    user_agent: asd.client_auth_info.user_agent,
                     key_object,
+                    credentials_submitted: false,
+                    email_link_tx: asd.email_link_tx,
                 };
 
                 let as_state = AuthState::Continue(allow);
@@ -1367,9 +2105,28 @@ impl AuthSession {
                 security_error!("Unable to select a credential for authentication");
                 (None, AuthState::Denied(BAD_CREDENTIALS.to_string()))
             }
+            State::UntrustedNetwork => {
+                security_info!(
+                    source = ?asd.client_auth_info.source,
+                    "account policy denies reauthentication from this network"
+                );
+                (None, AuthState::Denied(UNTRUSTED_NETWORK.to_string()))
+            }
         }
     }
 
+    pub(crate) fn account_uuid(&self) -> Uuid {
+        self.account.uuid
+    }
+
+    pub(crate) fn account_spn(&self) -> &str {
+        &self.account.spn
+    }
+
+    pub(crate) fn source(&self) -> &Source {
+        &self.source
+    }
+
     /// If the credential class can be softlocked, retrieve the credential ID. This is
     /// only used when a credential requires softlocking.
     pub fn get_credential_uuid(&self) -> Result<Option<Uuid>, OperationError> {
@@ -1382,7 +2139,11 @@ impl AuthSession {
             AuthSessionState::InProgress(CredHandler::Anonymous { .. })
             | AuthSessionState::InProgress(CredHandler::PasswordSecurityKey { .. })
             | AuthSessionState::InProgress(CredHandler::Passkey { .. })
-            | AuthSessionState::InProgress(CredHandler::AttestedPasskey { .. }) => Ok(None),
+            | AuthSessionState::InProgress(CredHandler::AttestedPasskey { .. })
+            | AuthSessionState::InProgress(CredHandler::DiscoverablePasskey { .. })
+            | AuthSessionState::InProgress(CredHandler::EmailLink { .. })
+            | AuthSessionState::InProgress(CredHandler::Federated { .. })
+            | AuthSessionState::InProgress(CredHandler::JwtAssertion { .. }) => Ok(None),
 
             AuthSessionState::Init(_) => {
                 debug!(
@@ -1397,13 +2158,30 @@ impl AuthSession {
         }
     }
 
+    /// The mechanism that is in progress, if one has been chosen and the session has not
+    /// yet completed.
+    pub fn current_mech(&self) -> Option<AuthMech> {
+        match &self.state {
+            AuthSessionState::InProgress(handler) => Some(handler.allows_mech()),
+            AuthSessionState::Init(_) | AuthSessionState::Success | AuthSessionState::Denied(_) => {
+                None
+            }
+        }
+    }
+
+    /// Record that a credential has been presented, returning true if it is the first in
+    /// this session.
+    pub fn record_first_submission(&mut self) -> bool {
+        !std::mem::replace(&mut self.credentials_submitted, true)
+    }
+
     /// Given the users indicated and preferred authentication mechanism that they want to proceed
     /// with, select the credential handler and begin the process of stepping through the
     /// authentication process.
     pub fn start_session(
         &mut self,
         mech: &AuthMech,
-        // time: &Duration,
+        ct: Duration,
         // webauthn: &WebauthnCore,
     ) -> Result<AuthState, OperationError> {
         // Given some auth mech, select which credential(s) are appropriate
@@ -1428,10 +2206,24 @@ impl AuthSession {
                     .cloned()
                     .collect();
 
-                if let Some(allowed_handler) = allowed_handlers.pop() {
+                if let Some(mut allowed_handler) = allowed_handlers.pop() {
                     let allowed: Vec<_> = allowed_handler.next_auth_allowed();
 
-                    if allowed.is_empty() {
+                    // The link is only sent once this mechanism is chosen.
+                    let email_link_sent = match &mut allowed_handler {
+                        CredHandler::EmailLink { link, .. } => Self::send_email_link(
+                            link,
+                            &self.account,
+                            &self.key_object,
+                            self.email_link_tx.as_ref(),
+                            ct,
+                        ),
+                        _ => Ok(()),
+                    };
+
+                    if let Err(err) = email_link_sent {
+                        (None, Err(err))
+                    } else if allowed.is_empty() {
                         security_info!("Unable to negotiate credentials");
                         (
                             None,
@@ -1462,6 +2254,88 @@ impl AuthSession {
         response
     }
 
+    /// The person has returned from an upstream identity provider. Select the request that
+    /// the provider returned with, and provide what is needed to exchange the code.
+    pub fn federated_exchange(
+        &mut self,
+        state: &str,
+    ) -> Result<OidcUpstreamExchange, OperationError> {
+        let AuthSessionState::InProgress(CredHandler::Federated { federated, .. }) =
+            &mut self.state
+        else {
+            security_error!("Upstream login returned to a session that did not begin one");
+            return Err(OperationError::AU0008FederatedRequestInvalid);
+        };
+
+        let Some(idx) = federated
+            .requests
+            .iter()
+            .position(|request| request.state() == state)
+        else {
+            security_error!("Upstream login returned with a state unknown to this session");
+            return Err(OperationError::AU0008FederatedRequestInvalid);
+        };
+
+        if federated.selected.replace(idx).is_some() {
+            security_error!("Upstream login has already returned to this session");
+            federated.state = CredVerifyState::Fail;
+            return Err(OperationError::AU0008FederatedRequestInvalid);
+        }
+
+        Ok(federated.requests[idx].exchange(&federated.redirect_uri))
+    }
+
+    /// Verify the id token that the selected upstream identity provider issued. The result is
+    /// held until the matching credential is presented.
+    pub fn federated_verify(
+        &mut self,
+        id_token: &str,
+        jwks: &JwkKeySet,
+        ct: Duration,
+    ) -> Result<(), OperationError> {
+        let AuthSessionState::InProgress(CredHandler::Federated { federated, .. }) =
+            &mut self.state
+        else {
+            return Err(OperationError::AU0008FederatedRequestInvalid);
+        };
+
+        let request = federated
+            .selected
+            .and_then(|idx| federated.requests.get(idx))
+            .ok_or(OperationError::AU0008FederatedRequestInvalid)?;
+
+        if federated.verified.is_some() {
+            security_error!("Upstream login has already been verified for this session");
+            return Err(OperationError::AU0008FederatedRequestInvalid);
+        }
+
+        federated.verified = Some(request.verify(id_token, jwks, &federated.mails, ct));
+        Ok(())
+    }
+
+    /// Sign a token for the email link, and queue the link to be sent to the account.
+    fn send_email_link(
+        link: &mut CredEmailLink,
+        account: &Account,
+        key_object: &KeyObject,
+        email_link_tx: Option<&Sender<EmailLinkMessage>>,
+        ct: Duration,
+    ) -> Result<(), OperationError> {
+        let email_link_tx = email_link_tx.ok_or(OperationError::KG004MailNotConfigured)?;
+
+        let (message, expiry) = email_link_issue(account, &link.mail, key_object, ct)?;
+        let token = message.token.clone();
+
+        email_link_tx.send(message).map_err(|_| {
+            error!("Unable to queue email link for delivery");
+            OperationError::KG005MailDeliveryFailed
+        })?;
+
+        security_info!(uuid = %account.uuid, "Sent email link");
+        link.sent = Some((token, expiry));
+        Ok(())
+    }
+
     /// Conduct a step of the authentication process. This validates the next credential factor
     /// presented and returns a result of Success, Continue, or Denied. Only in the success
     /// case is a UAT granted -- all others do not, including raised operation errors.
@@ -1490,8 +2364,24 @@ impl AuthSession {
                     pw_badlist,
                 ) {
                     CredState::Success { auth_type, cred_id } => {
+                        let factors = handler.used_factors();
+
+                        if self.password_expired(auth_type, time) {
+                            // The password was correct, but must be changed before a session is
+                            // issued. The token allows a credential update session to be started.
+                            let token = password_expired_token_issue(
+                                &self.account,
+                                &self.key_object,
+                                time,
+                            )?;
+
+                            security_info!("Password has expired, credential update required");
+                            self.state = AuthSessionState::Denied(PASSWORD_EXPIRED_MSG);
+                            return Ok(AuthState::CredentialUpdateRequired(Box::new(token)));
+                        }
+
                         // Issue the uat based on a set of factors.
-                        let uat = self.issue_uat(auth_type, time, async_tx, cred_id)?;
+                        let uat = self.issue_uat(auth_type, time, async_tx, cred_id, factors)?;
 
                         let jwt = Jws::into_json(&uat).map_err(|e| {
                             admin_error!(?e, "Failed to serialise into Jws");
@@ -1554,12 +2444,39 @@ impl AuthSession {
         response
     }
 
+    /// If account policy limits the age of passwords, an initial authentication with an
+    /// expired password must change it before a session is issued.
+    fn password_expired(&self, auth_type: AuthType, time: Duration) -> bool {
+        if !matches!(self.intent, AuthIntent::InitialAuth { .. }) {
+            return false;
+        }
+
+        match auth_type {
+            AuthType::Password
+            | AuthType::PasswordTotp
+            | AuthType::PasswordBackupCode
+            | AuthType::PasswordSecurityKey => self
+                .account_policy
+                .pw_max_age()
+                .is_some_and(|max_age| self.account.password_expired(max_age, time)),
+            AuthType::Anonymous
+            | AuthType::GeneratedPassword
+            | AuthType::Passkey
+            | AuthType::AttestedPasskey
+            | AuthType::EmailLink
+            | AuthType::Federated
+            | AuthType::JwtAssertion
+            | AuthType::Impersonation => false,
+        }
+    }
+
     fn issue_uat(
         &mut self,
         auth_type: AuthType,
         time: Duration,
         async_tx: &Sender<DelayedAction>,
         cred_id: Uuid,
+        factors: Vec<CredentialFactor>,
     ) -> Result<UserAuthToken, OperationError> {
         security_debug!("Successful cred handling");
         match self.intent {
@@ -1568,8 +2485,21 @@ impl AuthSession {
                 // We need to actually work this out better, and then
                 // pass it to to_userauthtoken
                 let scope = match auth_type {
+                    AuthType::Impersonation => {
+                        error!(
+                            "Impersonation sessions can't be issued by authentication. Rejecting"
+                        );
+                        return Err(OperationError::InvalidState);
+                    }
                     AuthType::Anonymous => SessionScope::ReadOnly,
                     AuthType::GeneratedPassword => SessionScope::ReadWrite,
+                    // An email link proves access to the mailbox, and an upstream login proves
+                    // an identity held elsewhere, not a credential of the account, so neither
+                    // directly grants privileges.
+                    AuthType::EmailLink | AuthType::Federated => SessionScope::PrivilegeCapable,
+                    // Service accounts can not reauthenticate, so these sessions are read write
+                    // from the start, and their lifetime is limited below.
+                    AuthType::JwtAssertion => SessionScope::ReadWrite,
                     AuthType::Password
                     | AuthType::PasswordTotp
                     | AuthType::PasswordBackupCode
@@ -1593,10 +2523,19 @@ impl AuthSession {
                     self.account.uuid
                 );
 
-                let uat = self
+                let mut uat = self
                     .account
                     .to_userauthtoken(session_id, scope, time, &self.account_policy)
                     .ok_or(OperationError::AU0004UserAuthTokenInvalid)?;
+                uat.session_key.clone_from(&self.session_key);
+
+                if auth_type == AuthType::JwtAssertion {
+                    let limit = uat.issued_at + Duration::from_secs(JWT_ASSERTION_SESSION_EXPIRY);
+                    uat.expiry = Some(uat.expiry.map_or(limit, |expiry| expiry.min(limit)));
+                    if let UatPurpose::ReadWrite { expiry } = &mut uat.purpose {
+                        *expiry = Some(expiry.map_or(limit, |expiry| expiry.min(limit)));
+                    }
+                }
 
                 // Queue the session info write.
                 // This is dependent on the type of authentication factors
@@ -1604,7 +2543,7 @@ impl AuthSession {
                 // safety barrier for auth types that shouldn't be here. Generally we
                 // submit session info for everything else.
                 match auth_type {
-                    AuthType::Anonymous => {
+                    AuthType::Anonymous | AuthType::Impersonation => {
                         // Skip - these sessions are not validated by session id.
                     }
                     AuthType::Password
@@ -1613,7 +2552,10 @@ impl AuthSession {
                     | AuthType::PasswordBackupCode
                     | AuthType::PasswordSecurityKey
                     | AuthType::Passkey
-                    | AuthType::AttestedPasskey => {
+                    | AuthType::AttestedPasskey
+                    | AuthType::EmailLink
+                    | AuthType::Federated
+                    | AuthType::JwtAssertion => {
                         trace!("⚠️   Queued AuthSessionRecord for {}", self.account.uuid);
                         async_tx.send(DelayedAction::AuthSessionRecord(AuthSessionRecord {
                             target_uuid: self.account.uuid,
@@ -1625,6 +2567,12 @@ impl AuthSession {
                             issued_by: IdentityId::User(self.account.uuid),
                             scope,
                             type_: auth_type,
+                            factors,
+                            source: match &self.source {
+                                Source::Https(ip) | Source::Ldaps(ip) => Some(ip.to_string()),
+                                Source::Internal => None,
+                            },
+                            user_agent: self.user_agent.clone(),
                         }))
                         .map_err(|e| {
                             debug!(?e, "queue failure");
@@ -1643,7 +2591,12 @@ impl AuthSession {
                 // Sanity check - We have already been really strict about what session types
                 // can actually trigger a re-auth, but we recheck here for paranoia!
                 let scope = match auth_type {
-                    AuthType::Anonymous | AuthType::GeneratedPassword => {
+                    AuthType::Anonymous
+                    | AuthType::GeneratedPassword
+                    | AuthType::EmailLink
+                    | AuthType::Federated
+                    | AuthType::JwtAssertion
+                    | AuthType::Impersonation => {
                         error!("AuthType used in Reauth is not valid for session re-issuance. Rejecting");
                         return Err(OperationError::AU0006CredentialMayNotReauthenticate);
                     }
@@ -1655,7 +2608,7 @@ impl AuthSession {
                     | AuthType::AttestedPasskey => SessionScope::PrivilegeCapable,
                 };
 
-                let uat = self
+                let mut uat = self
                     .account
                     .to_reissue_userauthtoken(
                         session_id,
@@ -1665,6 +2618,7 @@ impl AuthSession {
                         &self.account_policy,
                     )
                     .ok_or(OperationError::AU0007UserAuthTokenInvalid)?;
+                uat.session_key.clone_from(&self.session_key);
 
                 Ok(uat)
             }
@@ -1678,6 +2632,26 @@ impl AuthSession {
         Ok(AuthState::Denied(reason.to_string()))
     }
 
+    /// End the session as the credential is softlocked. If the lock lasts until a known
+    /// time, it is included so the user knows when they may try again.
+    pub fn end_session_softlocked(
+        &mut self,
+        locked_until: Option<Duration>,
+    ) -> Result<AuthState, OperationError> {
+        match locked_until {
+            Some(until) => {
+                let until = OffsetDateTime::UNIX_EPOCH + until;
+                self.state = AuthSessionState::Denied(ACCOUNT_LOCKED_OUT);
+                Ok(AuthState::Denied(format!(
+                    "{}, try again after {}",
+                    ACCOUNT_LOCKED_OUT,
+                    until.format(&Rfc3339).unwrap_or_else(|_| until.to_string())
+                )))
+            }
+            None => self.end_session(ACCOUNT_SOFTLOCKED),
+        }
+    }
+
     fn valid_auth_mechs(&self) -> Vec<AuthMech> {
         match &self.state {
             AuthSessionState::Success
@@ -1696,7 +2670,10 @@ impl AuthSession {
 mod tests {
     use std::time::Duration;
 
-    use compact_jwt::{dangernoverify::JwsDangerReleaseWithoutVerify, JwsVerifier};
+    use compact_jwt::{
+        dangernoverify::JwsDangerReleaseWithoutVerify, JwsEs256Signer, JwsSignerToVerifier,
+        JwsVerifier,
+    };
     use hashbrown::HashSet;
     use kanidm_proto::internal::{UatPurpose, UserAuthToken};
     use kanidm_proto::v1::{AuthAllowed, AuthCredential, AuthIssueSession, AuthMech};
@@ -1711,15 +2688,19 @@ mod tests {
     use crate::idm::accountpolicy::ResolvedAccountPolicy;
     use crate::idm::audit::AuditEvent;
     use crate::idm::authsession::{
-        AuthSession, AuthSessionData, BAD_AUTH_TYPE_MSG, BAD_BACKUPCODE_MSG, BAD_PASSWORD_MSG,
-        BAD_TOTP_MSG, BAD_WEBAUTHN_MSG, PW_BADLIST_MSG,
+        AuthSession, AuthSessionData, DiscoverableChallenge, ACCOUNT_EXPIRED, ACCOUNT_LOCKED_OUT,
+        BAD_AUTH_TYPE_MSG, BAD_BACKUPCODE_MSG, BAD_CREDENTIALS, BAD_JWT_ASSERTION_MSG,
+        BAD_PASSWORD_MSG, BAD_TOTP_MSG, BAD_WEBAUTHN_MSG, CREDENTIAL_SOFTLOCKED, PW_BADLIST_MSG,
+        UNTRUSTED_NETWORK, UNUSUAL_LOCATION,
     };
     use crate::idm::delayed::DelayedAction;
-    use crate::idm::AuthState;
+    use crate::idm::jwtassertion::test_jwt_assertion;
+    use crate::idm::{AuthDeniedKind, AuthState};
     use crate::migration_data::{BUILTIN_ACCOUNT_ANONYMOUS, BUILTIN_ACCOUNT_TEST_PERSON};
     use crate::prelude::*;
     use crate::server::keys::KeyObjectInternal;
     use crate::utils::readable_password_from_random;
+    use crate::value::{AuthType, CredentialFactor, CredentialType};
     use kanidm_lib_crypto::CryptoPolicy;
 
     fn create_pw_badlist_cache() -> HashSet<String> {
@@ -1737,6 +2718,43 @@ mod tests {
         .unwrap()
     }
 
+    #[test]
+    fn test_idm_authsession_denied_kind() {
+        // Which credential was incorrect is not revealed.
+        for reason in [
+            BAD_PASSWORD_MSG,
+            BAD_TOTP_MSG,
+            BAD_WEBAUTHN_MSG,
+            BAD_BACKUPCODE_MSG,
+            BAD_CREDENTIALS,
+        ] {
+            assert_eq!(
+                AuthDeniedKind::from_reason(reason),
+                AuthDeniedKind::CredentialsIncorrect
+            );
+        }
+
+        assert_eq!(
+            AuthDeniedKind::from_reason(CREDENTIAL_SOFTLOCKED),
+            AuthDeniedKind::AccountLocked
+        );
+        assert_eq!(
+            AuthDeniedKind::from_reason(ACCOUNT_EXPIRED),
+            AuthDeniedKind::AccountExpired
+        );
+        assert_eq!(
+            AuthDeniedKind::from_reason(UNTRUSTED_NETWORK),
+            AuthDeniedKind::PolicyDenied
+        );
+        assert_eq!(
+            AuthDeniedKind::from_reason(&format!(
+                "{}, try again after 1970-01-01T00:01:00Z",
+                ACCOUNT_LOCKED_OUT
+            )),
+            AuthDeniedKind::AccountLocked
+        );
+    }
+
     #[test]
     fn test_idm_authsession_anonymous_auth_mech() {
         sketching::test_init();
@@ -1752,6 +2770,9 @@ mod tests {
             webauthn: &webauthn,
             ct: duration_from_epoch_now(),
             client_auth_info: Source::Internal.into(),
+            email_link_tx: None,
+            oidc_upstreams: Vec::new(),
+            unusual_location: false,
         };
 
         let key_object = KeyObjectInternal::new_test();
@@ -1764,7 +2785,7 @@ mod tests {
 
         let state = session
             .expect("Missing auth session?")
-            .start_session(&AuthMech::Anonymous)
+            .start_session(&AuthMech::Anonymous, duration_from_epoch_now())
             .expect("Failed to select anonymous mech.");
 
         if let AuthState::Continue(auth_mechs) = state {
@@ -1776,6 +2797,142 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_idm_authsession_untrusted_network() {
+        sketching::test_init();
+        let webauthn = create_webauthn();
+
+        let mut account: Account = BUILTIN_ACCOUNT_TEST_PERSON.clone().into();
+        let p = CryptoPolicy::minimum();
+        let cred = Credential::new_password_only(&p, "test_password").unwrap();
+        account.primary = Some(cred.clone());
+
+        let trusted_source = Source::Https("10.0.0.1".parse().unwrap());
+        let untrusted_source = Source::Https("203.0.113.1".parse().unwrap());
+
+        let start = |account: &Account, policy: ResolvedAccountPolicy, source: &Source| {
+            let asd = AuthSessionData {
+                account: account.clone(),
+                account_policy: policy,
+                issue: AuthIssueSession::Token,
+                webauthn: &webauthn,
+                ct: duration_from_epoch_now(),
+                client_auth_info: source.clone().into(),
+                email_link_tx: None,
+                oidc_upstreams: Vec::new(),
+                unusual_location: false,
+            };
+            let key_object = KeyObjectInternal::new_test();
+            AuthSession::new(asd, false, key_object).1
+        };
+
+        let mfa_policy =
+            || ResolvedAccountPolicy::test_network_policy(&["10.0.0.0/8"], CredentialType::Mfa);
+
+        // From a trusted network a password is enough.
+        match start(&account, mfa_policy(), &trusted_source) {
+            AuthState::Choose(auth_mechs) => {
+                assert!(auth_mechs.iter().any(|x| matches!(x, AuthMech::Password)))
+            }
+            _ => panic!("Invalid auth state"),
+        }
+
+        // But not from anywhere else.
+        match start(&account, mfa_policy(), &untrusted_source) {
+            AuthState::Denied(msg) => assert_eq!(msg, UNTRUSTED_NETWORK),
+            _ => panic!("Invalid auth state"),
+        }
+
+        // Once the account has mfa, it can be used from the untrusted network.
+        let totp = Totp::generate_secure(TOTP_DEFAULT_STEP);
+        account.primary = Some(cred.append_totp("totp".to_string(), totp));
+
+        match start(&account, mfa_policy(), &untrusted_source) {
+            AuthState::Choose(auth_mechs) => {
+                assert!(auth_mechs
+                    .iter()
+                    .any(|x| matches!(x, AuthMech::PasswordTotp)))
+            }
+            _ => panic!("Invalid auth state"),
+        }
+
+        // A policy can deny the untrusted network entirely.
+        let deny_policy =
+            ResolvedAccountPolicy::test_network_policy(&["10.0.0.0/8"], CredentialType::Invalid);
+
+        match start(&account, deny_policy, &untrusted_source) {
+            AuthState::Denied(msg) => assert_eq!(msg, UNTRUSTED_NETWORK),
+            _ => panic!("Invalid auth state"),
+        }
+
+        // Internal authentication is never restricted by network.
+        let deny_policy =
+            ResolvedAccountPolicy::test_network_policy(&["10.0.0.0/8"], CredentialType::Invalid);
+
+        match start(&account, deny_policy, &Source::Internal) {
+            AuthState::Choose(_) => {}
+            _ => panic!("Invalid auth state"),
+        }
+    }
+
+    #[test]
+    fn test_idm_authsession_unusual_location() {
+        sketching::test_init();
+        let webauthn = create_webauthn();
+
+        let mut account: Account = BUILTIN_ACCOUNT_TEST_PERSON.clone().into();
+        let p = CryptoPolicy::minimum();
+        let cred = Credential::new_password_only(&p, "test_password").unwrap();
+        account.primary = Some(cred.clone());
+
+        let start = |account: &Account, unusual_location: bool| {
+            let asd = AuthSessionData {
+                account: account.clone(),
+                account_policy: ResolvedAccountPolicy::default(),
+                issue: AuthIssueSession::Token,
+                webauthn: &webauthn,
+                ct: duration_from_epoch_now(),
+                client_auth_info: Source::Https("203.0.113.1".parse().unwrap()).into(),
+                email_link_tx: None,
+                oidc_upstreams: Vec::new(),
+                unusual_location,
+            };
+            let key_object = KeyObjectInternal::new_test();
+            AuthSession::new(asd, false, key_object).1
+        };
+
+        // A password is enough from the usual locations of the account.
+        match start(&account, false) {
+            AuthState::Choose(auth_mechs) => {
+                assert!(auth_mechs.iter().any(|x| matches!(x, AuthMech::Password)))
+            }
+            _ => panic!("Invalid auth state"),
+        }
+
+        // But not from somewhere unusual.
+        match start(&account, true) {
+            AuthState::Denied(msg) => assert_eq!(msg, UNUSUAL_LOCATION),
+            _ => panic!("Invalid auth state"),
+        }
+        assert_eq!(
+            AuthDeniedKind::from_reason(UNUSUAL_LOCATION),
+            AuthDeniedKind::PolicyDenied
+        );
+
+        // Multi-factor credentials can still be used.
+        let totp = Totp::generate_secure(TOTP_DEFAULT_STEP);
+        account.primary = Some(cred.append_totp("totp".to_string(), totp));
+
+        match start(&account, true) {
+            AuthState::Choose(auth_mechs) => {
+                assert!(auth_mechs
+                    .iter()
+                    .any(|x| matches!(x, AuthMech::PasswordTotp)))
+            }
+            _ => panic!("Invalid auth state"),
+        }
+    }
+
     macro_rules! start_password_session {
         (
             $audit:expr,
@@ -1790,6 +2947,9 @@ mod tests {
                 webauthn: $webauthn,
                 ct: duration_from_epoch_now(),
                 client_auth_info: Source::Internal.into(),
+                email_link_tx: None,
+                oidc_upstreams: Vec::new(),
+                unusual_location: false,
             };
             let key_object = KeyObjectInternal::new_test();
             let (session, state) = AuthSession::new(asd, $privileged, key_object);
@@ -1802,7 +2962,7 @@ mod tests {
             }
 
             let state = session
-                .start_session(&AuthMech::Password)
+                .start_session(&AuthMech::Password, duration_from_epoch_now())
                 .expect("Failed to select anonymous mech.");
 
             if let AuthState::Continue(auth_mechs) = state {
@@ -1969,6 +3129,9 @@ mod tests {
             webauthn,
             ct: duration_from_epoch_now(),
             client_auth_info: Source::Internal.into(),
+            email_link_tx: None,
+            oidc_upstreams: Vec::new(),
+            unusual_location: false,
         };
         let key_object = KeyObjectInternal::new_test();
         let (session, state) = AuthSession::new(asd, false, key_object);
@@ -1983,7 +3146,7 @@ mod tests {
         }
 
         let state = session
-            .start_session(&AuthMech::PasswordTotp)
+            .start_session(&AuthMech::PasswordTotp, duration_from_epoch_now())
             .expect("Failed to select password totp mech.");
 
         if let AuthState::Continue(auth_mechs) = state {
@@ -2009,6 +3172,9 @@ mod tests {
             webauthn,
             ct: duration_from_epoch_now(),
             client_auth_info: Source::Internal.into(),
+            email_link_tx: None,
+            oidc_upstreams: Vec::new(),
+            unusual_location: false,
         };
         let key_object = KeyObjectInternal::new_test();
         let (session, state) = AuthSession::new(asd, false, key_object);
@@ -2023,7 +3189,7 @@ mod tests {
         }
 
         let state = session
-            .start_session(&AuthMech::PasswordSecurityKey)
+            .start_session(&AuthMech::PasswordSecurityKey, duration_from_epoch_now())
             .expect("Failed to select password security key mech.");
 
         let mut rchal = None;
@@ -2054,6 +3220,9 @@ mod tests {
             webauthn,
             ct: duration_from_epoch_now(),
             client_auth_info: Source::Internal.into(),
+            email_link_tx: None,
+            oidc_upstreams: Vec::new(),
+            unusual_location: false,
         };
         let key_object = KeyObjectInternal::new_test();
         let (session, state) = AuthSession::new(asd, false, key_object);
@@ -2068,7 +3237,7 @@ mod tests {
         }
 
         let state = session
-            .start_session(&AuthMech::PasswordBackupCode)
+            .start_session(&AuthMech::PasswordBackupCode, duration_from_epoch_now())
             .expect("Failed to select password backup code mech.");
 
         if let AuthState::Continue(auth_mechs) = state {
@@ -2346,6 +3515,9 @@ mod tests {
                 webauthn: $webauthn,
                 ct: duration_from_epoch_now(),
                 client_auth_info: Source::Internal.into(),
+                email_link_tx: None,
+                oidc_upstreams: Vec::new(),
+                unusual_location: false,
             };
             let key_object = KeyObjectInternal::new_test();
             let (session, state) = AuthSession::new(asd, false, key_object);
@@ -2358,7 +3530,7 @@ mod tests {
             }
 
             let state = session
-                .start_session(&AuthMech::Passkey)
+                .start_session(&AuthMech::Passkey, duration_from_epoch_now())
                 .expect("Failed to select Passkey mech.");
 
             let wan_chal = if let AuthState::Continue(auth_mechs) = state {
@@ -2588,6 +3760,64 @@ mod tests {
         assert!(audit_rx.blocking_recv().is_none());
     }
 
+    #[test]
+    fn test_idm_authsession_discoverable_passkey() {
+        sketching::test_init();
+        let (async_tx, _async_rx) = unbounded();
+        let (audit_tx, _audit_rx) = unbounded();
+        let ts = duration_from_epoch_now();
+        let mut account: Account = BUILTIN_ACCOUNT_TEST_PERSON.clone().into();
+
+        let (webauthn, _wa, wan_cred) = setup_webauthn_passkey(account.name.as_str());
+
+        let start = |account: &Account| {
+            let asd = AuthSessionData {
+                account: account.clone(),
+                account_policy: ResolvedAccountPolicy::default(),
+                issue: AuthIssueSession::Token,
+                webauthn: &webauthn,
+                ct: ts,
+                client_auth_info: Source::Internal.into(),
+                email_link_tx: None,
+                oidc_upstreams: Vec::new(),
+                unusual_location: false,
+            };
+            let challenge =
+                DiscoverableChallenge::new(&webauthn).expect("Failed to create challenge");
+            AuthSession::new_discoverable(asd, challenge, KeyObjectInternal::new_test())
+        };
+
+        // An account without passkeys can't be used.
+        let (session, state) = start(&account);
+        assert!(session.is_none());
+        assert!(matches!(state, AuthState::Denied(msg) if msg == BAD_CREDENTIALS));
+
+        account.passkeys = btreemap![(Uuid::new_v4(), ("soft".to_string(), wan_cred))];
+
+        // The passkey handler is already selected.
+        let (session, state) = start(&account);
+        let mut session = session.expect("Missing auth session");
+        assert!(matches!(
+            state,
+            AuthState::Continue(allowed)
+                if matches!(allowed.as_slice(), [AuthAllowed::Passkey(_)])
+        ));
+        assert_eq!(session.current_mech(), Some(AuthMech::Passkey));
+
+        // Other credentials are rejected.
+        match session.validate_creds(
+            &AuthCredential::Anonymous,
+            ts,
+            &async_tx,
+            &audit_tx,
+            &webauthn,
+            &Default::default(),
+        ) {
+            Ok(AuthState::Denied(msg)) => assert_eq!(msg, BAD_AUTH_TYPE_MSG),
+            _ => panic!(),
+        };
+    }
+
     #[test]
     fn test_idm_authsession_webauthn_password_mech() {
         sketching::test_init();
@@ -3366,4 +4596,181 @@ mod tests {
         drop(audit_tx);
         assert!(audit_rx.blocking_recv().is_none());
     }
+
+    fn start_jwt_assertion_session(
+        account: &Account,
+        webauthn: &Webauthn,
+    ) -> (AuthSession, String) {
+        let asd = AuthSessionData {
+            account: account.clone(),
+            account_policy: ResolvedAccountPolicy::default(),
+            issue: AuthIssueSession::Token,
+            webauthn,
+            ct: duration_from_epoch_now(),
+            client_auth_info: Source::Internal.into(),
+            email_link_tx: None,
+            oidc_upstreams: Vec::new(),
+            unusual_location: false,
+        };
+        let key_object = KeyObjectInternal::new_test();
+        let (session, state) = AuthSession::new(asd, false, key_object);
+        let mut session = session.expect("Session was unable to be created.");
+
+        if let AuthState::Choose(auth_mechs) = state {
+            assert!(auth_mechs
+                .iter()
+                .any(|x| matches!(x, AuthMech::JwtAssertion)))
+        } else {
+            panic!();
+        }
+
+        let state = session
+            .start_session(&AuthMech::JwtAssertion, duration_from_epoch_now())
+            .expect("Failed to select jwt assertion mech.");
+
+        let AuthState::Continue(mut auth_mechs) = state else {
+            panic!("Invalid auth state")
+        };
+
+        let Some(AuthAllowed::JwtAssertion(nonce)) = auth_mechs.pop() else {
+            panic!("Invalid auth mech")
+        };
+
+        (session, nonce)
+    }
+
+    #[test]
+    fn test_idm_authsession_jwt_assertion_mech() {
+        sketching::test_init();
+        let webauthn = create_webauthn();
+        let pw_badlist_cache = create_pw_badlist_cache();
+        let ts = duration_from_epoch_now();
+
+        let signer = JwsEs256Signer::generate_es256().expect("Unable to create signer");
+        let der = signer
+            .get_verifier()
+            .and_then(|verifier| verifier.public_key_to_der())
+            .expect("Unable to export key");
+
+        let mut account: Account = BUILTIN_ACCOUNT_TEST_PERSON.clone().into();
+        account.jwt_public_keys.insert("deploy".to_string(), der);
+
+        let (async_tx, mut async_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = unbounded();
+
+        // An assertion for a different authentication is rejected.
+        let (mut session, nonce) = start_jwt_assertion_session(&account, &webauthn);
+        let assertion = test_jwt_assertion(
+            &signer,
+            &account.spn,
+            "https://idm.example.com",
+            "not the nonce",
+            ts,
+        );
+        match session.validate_creds(
+            &AuthCredential::JwtAssertion(assertion),
+            ts,
+            &async_tx,
+            &audit_tx,
+            &webauthn,
+            &pw_badlist_cache,
+        ) {
+            Ok(AuthState::Denied(msg)) => assert_eq!(msg, BAD_JWT_ASSERTION_MSG),
+            _ => panic!(),
+        };
+
+        match audit_rx.try_recv() {
+            Ok(AuditEvent::AuthenticationDenied { .. }) => {}
+            _ => panic!("Oh no"),
+        }
+
+        // A fresh session has a new nonce, so the assertion for the first is also rejected.
+        let assertion =
+            test_jwt_assertion(&signer, &account.spn, "https://idm.example.com", &nonce, ts);
+        let (mut session, _) = start_jwt_assertion_session(&account, &webauthn);
+        match session.validate_creds(
+            &AuthCredential::JwtAssertion(assertion),
+            ts,
+            &async_tx,
+            &audit_tx,
+            &webauthn,
+            &pw_badlist_cache,
+        ) {
+            Ok(AuthState::Denied(msg)) => assert_eq!(msg, BAD_JWT_ASSERTION_MSG),
+            _ => panic!(),
+        };
+
+        match audit_rx.try_recv() {
+            Ok(AuditEvent::AuthenticationDenied { .. }) => {}
+            _ => panic!("Oh no"),
+        }
+
+        // An expired assertion is rejected.
+        let (mut session, nonce) = start_jwt_assertion_session(&account, &webauthn);
+        let assertion =
+            test_jwt_assertion(&signer, &account.spn, "https://idm.example.com", &nonce, ts);
+        match session.validate_creds(
+            &AuthCredential::JwtAssertion(assertion),
+            ts + Duration::from_secs(JWT_ASSERTION_MAX_LIFETIME),
+            &async_tx,
+            &audit_tx,
+            &webauthn,
+            &pw_badlist_cache,
+        ) {
+            Ok(AuthState::Denied(msg)) => assert_eq!(msg, BAD_JWT_ASSERTION_MSG),
+            _ => panic!(),
+        };
+
+        match audit_rx.try_recv() {
+            Ok(AuditEvent::AuthenticationDenied { .. }) => {}
+            _ => panic!("Oh no"),
+        }
+
+        // A valid assertion issues a short lived session.
+        let (mut session, nonce) = start_jwt_assertion_session(&account, &webauthn);
+        let assertion =
+            test_jwt_assertion(&signer, &account.spn, "https://idm.example.com", &nonce, ts);
+        let uat: UserAuthToken = match session.validate_creds(
+            &AuthCredential::JwtAssertion(assertion),
+            ts,
+            &async_tx,
+            &audit_tx,
+            &webauthn,
+            &pw_badlist_cache,
+        ) {
+            Ok(AuthState::Success(jwsc, AuthIssueSession::Token)) => {
+                let jws_verifier = JwsDangerReleaseWithoutVerify::default();
+
+                jws_verifier
+                    .verify(&*jwsc)
+                    .unwrap()
+                    .from_json::<UserAuthToken>()
+                    .unwrap()
+            }
+            _ => panic!(),
+        };
+
+        let limit = uat.issued_at + Duration::from_secs(JWT_ASSERTION_SESSION_EXPIRY);
+        assert_eq!(uat.expiry, Some(limit));
+        match uat.purpose {
+            UatPurpose::ReadOnly => panic!("Unexpected UatPurpose::ReadOnly"),
+            UatPurpose::ReadWrite { expiry } => assert_eq!(expiry, Some(limit)),
+        }
+
+        match async_rx.blocking_recv() {
+            Some(DelayedAction::AuthSessionRecord(record)) => {
+                assert_eq!(record.type_, AuthType::JwtAssertion);
+                assert_eq!(
+                    record.factors,
+                    vec![CredentialFactor::JwtAssertion("deploy".to_string())]
+                );
+            }
+            _ => panic!("Oh no"),
+        }
+
+        drop(async_tx);
+        assert!(async_rx.blocking_recv().is_none());
+        drop(audit_tx);
+        assert!(audit_rx.blocking_recv().is_none());
+    }
 }
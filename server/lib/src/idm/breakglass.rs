@@ -0,0 +1,591 @@
+//! Break glass authentication is an emergency path to authenticate the admin account when
+//! its credentials, or the key providers that sign sessions, are unavailable.
+//!
+//! A hardware-bound key is registered offline by providing its public key to the server in
+//! the configuration. The holder of the key proves possession by signing a challenge, and is
+//! then issued a session for the admin account. This session is signed by a key that only
+//! exists in memory, so it remains valid even if the key objects in the database can't be
+//! used. These sessions are always audited, can't be extended, and expire a short time after
+//! they are issued or when the server restarts.
+//!
+//! Emergency accounts may also be pre-provisioned with recovery codes that are generated
+//! offline at install time. Only a hash of each code is given to the server, and each code
+//! can only be used once, as it is removed from the file of codes before a session is issued.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use compact_jwt::{
+    Jwk, Jws, JwsCompact, JwsEs256Signer, JwsEs256Verifier, JwsSigner, JwsSignerToVerifier,
+    JwsVerifier,
+};
+use concread::bptree::BptreeMap;
+use kanidm_proto::internal::{UatPurpose, UserAuthToken};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Public};
+use openssl::sign::Verifier;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::utils::readable_password_from_random;
+
+/// How long a challenge may be used for, in seconds. This allows time for the key to be
+/// retrieved and the challenge signed.
+pub const BREAK_GLASS_CHALLENGE_TIMEOUT: u64 = 300;
+
+/// How long a break glass session is valid for, in seconds.
+pub const BREAK_GLASS_SESSION_EXPIRY: u64 = 900;
+
+/// The challenge is prefixed with this, so that a signature made by the key for any other
+/// purpose can't be used as a response.
+const BREAK_GLASS_CHALLENGE_CONTEXT: &[u8] = b"kanidm break glass authentication\n";
+
+const BREAK_GLASS_CHALLENGE_NONCE_LEN: usize = 32;
+
+/// How many recovery codes are generated for an emergency account by default.
+pub const BREAK_GLASS_CODE_DEFAULT_COUNT: usize = 8;
+
+/// The recovery codes of the emergency accounts. This is generated offline, and only holds a
+/// hash of each code, so the codes themselves are only displayed when they are generated.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BreakGlassCodeFile {
+    /// The name of each emergency account, and the SHA-256 hashes of its unused codes.
+    accounts: BTreeMap<String, Vec<String>>,
+}
+
+fn break_glass_code_hash(code: &str) -> String {
+    hex::encode(openssl::sha::sha256(code.trim().as_bytes()))
+}
+
+impl BreakGlassCodeFile {
+    pub fn read(path: &Path) -> Result<Self, OperationError> {
+        let data = fs::read(path).map_err(|err| {
+            error!(?err, path = %path.display(), "Unable to read break glass codes");
+            OperationError::InvalidState
+        })?;
+
+        serde_json::from_slice(&data).map_err(|err| {
+            error!(?err, path = %path.display(), "Invalid break glass codes");
+            OperationError::SerdeJsonError
+        })
+    }
+
+    /// Write the codes to a temporary file that then replaces the existing file, so that a
+    /// failure part way through can't leave the file truncated.
+    pub fn write(&self, path: &Path) -> Result<(), OperationError> {
+        let data = serde_json::to_vec_pretty(self).map_err(|err| {
+            error!(?err, "Unable to serialise break glass codes");
+            OperationError::SerdeJsonError
+        })?;
+
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        fs::write(&tmp_path, data)
+            .and_then(|()| fs::rename(&tmp_path, path))
+            .map_err(|err| {
+                error!(?err, path = %path.display(), "Unable to write break glass codes");
+                OperationError::InvalidState
+            })
+    }
+
+    /// Generate new recovery codes for the account, replacing any codes it already has. The
+    /// codes are returned so that they can be displayed, as they can't be recovered later.
+    pub fn generate(&mut self, account: &str, count: usize) -> Vec<String> {
+        let codes: Vec<_> = (0..count)
+            .map(|_| readable_password_from_random())
+            .collect();
+
+        self.accounts.insert(
+            account.to_string(),
+            codes
+                .iter()
+                .map(|code| break_glass_code_hash(code))
+                .collect(),
+        );
+
+        codes
+    }
+
+    /// The emergency accounts, and how many unused codes each has.
+    pub fn accounts(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.accounts
+            .iter()
+            .map(|(account, hashes)| (account.as_str(), hashes.len()))
+    }
+
+    /// Remove the code from the account, returning false if it is not an unused code of the
+    /// account.
+    fn consume(&mut self, account: &str, code: &str) -> bool {
+        let hash = break_glass_code_hash(code);
+        let Some(hashes) = self.accounts.get_mut(account) else {
+            return false;
+        };
+
+        let before = hashes.len();
+        hashes.retain(|existing| *existing != hash);
+        hashes.len() != before
+    }
+}
+
+struct BreakGlassCodes {
+    /// Used codes are removed from this file, so that they can't be used again after the
+    /// server restarts.
+    path: PathBuf,
+    file: Mutex<BreakGlassCodeFile>,
+}
+
+#[derive(Clone)]
+struct BreakGlassChallenge {
+    challenge: Vec<u8>,
+    expiry: Duration,
+}
+
+pub struct BreakGlass {
+    /// The public key of the hardware-bound key that was registered offline.
+    public_key: Option<PKey<Public>>,
+    codes: Option<BreakGlassCodes>,
+    /// Signs the sessions that are issued. This is regenerated each time the server starts.
+    signer: JwsEs256Signer,
+    verifier: JwsEs256Verifier,
+    challenges: BptreeMap<Uuid, BreakGlassChallenge>,
+    /// The sessions that have been issued, and the time they expire.
+    sessions: BptreeMap<Uuid, Duration>,
+}
+
+impl BreakGlass {
+    /// Load the public key of the break glass key, and the recovery codes of the emergency
+    /// accounts. The key must be an ECDSA P-256 key in PEM format. At least one of these
+    /// must be provided.
+    pub fn new(
+        public_key_pem: Option<&[u8]>,
+        codes_path: Option<&Path>,
+    ) -> Result<Self, OperationError> {
+        if public_key_pem.is_none() && codes_path.is_none() {
+            error!("Break glass authentication requires a key or recovery codes");
+            return Err(OperationError::InvalidState);
+        }
+
+        let public_key = public_key_pem
+            .map(|public_key_pem| {
+                let public_key = PKey::public_key_from_pem(public_key_pem).map_err(|err| {
+                    error!(?err, "Unable to parse the break glass public key");
+                    OperationError::InvalidState
+                })?;
+
+                let is_p256 = public_key
+                    .ec_key()
+                    .ok()
+                    .and_then(|ec_key| ec_key.group().curve_name())
+                    == Some(Nid::X9_62_PRIME256V1);
+
+                if !is_p256 {
+                    error!("The break glass public key must be an ECDSA P-256 key");
+                    return Err(OperationError::InvalidState);
+                }
+
+                Ok(public_key)
+            })
+            .transpose()?;
+
+        let codes = codes_path
+            .map(|path| {
+                BreakGlassCodeFile::read(path).map(|file| BreakGlassCodes {
+                    path: path.to_path_buf(),
+                    file: Mutex::new(file),
+                })
+            })
+            .transpose()?;
+
+        let signer = JwsEs256Signer::generate_es256().map_err(|err| {
+            error!(?err, "Unable to generate break glass session signing key");
+            OperationError::CryptographyError
+        })?;
+
+        let verifier = signer.get_verifier().map_err(|err| {
+            error!(?err, "Unable to produce break glass session verifier");
+            OperationError::CryptographyError
+        })?;
+
+        Ok(BreakGlass {
+            public_key,
+            codes,
+            signer,
+            verifier,
+            challenges: BptreeMap::new(),
+            sessions: BptreeMap::new(),
+        })
+    }
+
+    /// If a break glass key is registered, so that challenges can be issued.
+    pub(crate) fn has_key(&self) -> bool {
+        self.public_key.is_some()
+    }
+
+    /// The emergency accounts that have recovery codes, and how many unused codes each has.
+    pub fn emergency_accounts(&self) -> Vec<(String, usize)> {
+        self.codes
+            .as_ref()
+            .and_then(|codes| codes.file.lock().ok())
+            .map(|file| {
+                file.accounts()
+                    .map(|(account, count)| (account.to_string(), count))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Issue a challenge that must be signed by the break glass key.
+    pub(crate) fn challenge(&self, ct: Duration) -> (Uuid, Vec<u8>) {
+        let mut nonce = [0; BREAK_GLASS_CHALLENGE_NONCE_LEN];
+        thread_rng().fill(&mut nonce);
+
+        let mut challenge = BREAK_GLASS_CHALLENGE_CONTEXT.to_vec();
+        challenge.extend_from_slice(&nonce);
+
+        let challenge_id = Uuid::new_v4();
+        let mut challenge_write = self.challenges.write();
+        challenge_write.insert(
+            challenge_id,
+            BreakGlassChallenge {
+                challenge: challenge.clone(),
+                expiry: ct + Duration::from_secs(BREAK_GLASS_CHALLENGE_TIMEOUT),
+            },
+        );
+        challenge_write.commit();
+
+        (challenge_id, challenge)
+    }
+
+    /// Verify the DER encoded ECDSA signature of a challenge. The challenge is consumed
+    /// regardless of the outcome, so that it can only be attempted once.
+    pub(crate) fn verify_challenge(
+        &self,
+        challenge_id: Uuid,
+        signature: &[u8],
+        ct: Duration,
+    ) -> Result<(), OperationError> {
+        let mut challenge_write = self.challenges.write();
+        let challenge = challenge_write.remove(&challenge_id);
+        challenge_write.commit();
+
+        let Some(public_key) = &self.public_key else {
+            security_info!("No break glass key is registered");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let Some(challenge) = challenge.filter(|challenge| ct < challenge.expiry) else {
+            security_info!(?challenge_id, "Break glass challenge is not valid");
+            return Err(OperationError::InvalidSessionState);
+        };
+
+        let valid = Verifier::new(MessageDigest::sha256(), public_key)
+            .and_then(|mut verifier| {
+                verifier.update(&challenge.challenge)?;
+                verifier.verify(signature)
+            })
+            .unwrap_or_else(|err| {
+                security_info!(?err, "Unable to verify break glass signature");
+                false
+            });
+
+        if valid {
+            Ok(())
+        } else {
+            Err(OperationError::NotAuthenticated)
+        }
+    }
+
+    /// Verify a recovery code of an emergency account. The code is removed from the file of
+    /// codes before this succeeds, so that it can only be used once. If the file can't be
+    /// updated the code is not accepted.
+    pub(crate) fn verify_code(&self, account: &str, code: &str) -> Result<(), OperationError> {
+        let Some(codes) = &self.codes else {
+            security_info!("No break glass recovery codes are configured");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let mut file = codes.file.lock().map_err(|_| {
+            error!("Break glass recovery codes are poisoned");
+            OperationError::InvalidState
+        })?;
+
+        if !file.consume(account, code) {
+            security_info!(%account, "Break glass recovery code is not valid");
+            return Err(OperationError::NotAuthenticated);
+        }
+
+        file.write(&codes.path)
+    }
+
+    /// Issue a session for the account, signed by the in memory key.
+    pub(crate) fn issue(
+        &self,
+        uuid: Uuid,
+        spn: String,
+        displayname: String,
+        ct: Duration,
+    ) -> Result<(UserAuthToken, JwsCompact), OperationError> {
+        let issued_at = time::OffsetDateTime::UNIX_EPOCH + ct;
+        let expiry = issued_at + Duration::from_secs(BREAK_GLASS_SESSION_EXPIRY);
+
+        let uat = UserAuthToken {
+            session_id: Uuid::new_v4(),
+            issued_at,
+            expiry: Some(expiry),
+            purpose: UatPurpose::ReadWrite {
+                expiry: Some(expiry),
+            },
+            uuid,
+            displayname,
+            spn,
+            mail_primary: None,
+            ui_hints: BTreeSet::new(),
+            limit_search_max_results: None,
+            limit_search_max_filter_test: None,
+            impersonated_by: None,
+            session_key: None,
+            idle_timeout: None,
+        };
+
+        let jws = Jws::into_json(&uat).map_err(|err| {
+            error!(?err, "Failed to serialise break glass session");
+            OperationError::AU0002JwsSerialisation
+        })?;
+
+        let token = self.signer.sign(&jws).map_err(|err| {
+            error!(?err, "Failed to sign break glass session");
+            OperationError::AU0003JwsSignature
+        })?;
+
+        let mut session_write = self.sessions.write();
+        session_write.insert(
+            uat.session_id,
+            ct + Duration::from_secs(BREAK_GLASS_SESSION_EXPIRY),
+        );
+        session_write.commit();
+
+        Ok((uat, token))
+    }
+
+    /// Verify a token that was issued by break glass authentication. Returns `None` if the
+    /// token was not signed by this server's break glass key, so that it can be verified as
+    /// a normal session.
+    pub(crate) fn verify(
+        &self,
+        jwsu: &JwsCompact,
+        ct: Duration,
+    ) -> Result<Option<UserAuthToken>, OperationError> {
+        if jwsu.kid() != Some(self.signer.get_kid()) {
+            return Ok(None);
+        }
+
+        let uat = self
+            .verifier
+            .verify(jwsu)
+            .and_then(|jws| jws.from_json::<UserAuthToken>())
+            .map_err(|err| {
+                security_info!(?err, "Unable to verify break glass session");
+                OperationError::NotAuthenticated
+            })?;
+
+        let session_read = self.sessions.read();
+        match session_read.get(&uat.session_id) {
+            Some(expiry) if ct < *expiry => Ok(Some(uat)),
+            Some(_) => {
+                security_info!(session_id = ?uat.session_id, "Break glass session expired");
+                Err(OperationError::SessionExpired)
+            }
+            None => {
+                security_info!(session_id = ?uat.session_id, "Break glass session is not known");
+                Err(OperationError::NotAuthenticated)
+            }
+        }
+    }
+
+    /// The public key of the in memory key that signs break glass sessions, so that clients
+    /// can verify the sessions they are issued.
+    pub(crate) fn public_jwk(&self, key_id: &str) -> Result<Option<Jwk>, OperationError> {
+        if key_id != self.signer.get_kid() {
+            return Ok(None);
+        }
+
+        self.verifier.public_key_as_jwk().map(Some).map_err(|err| {
+            error!(?err, "Unable to construct break glass public JWK");
+            OperationError::KP0044KeyObjectJwsPublicJwk
+        })
+    }
+
+    /// Remove the challenges and sessions that have expired.
+    pub(crate) fn expire(&self, ct: Duration) {
+        let mut challenge_write = self.challenges.write();
+        let expired: Vec<_> = challenge_write
+            .iter()
+            .filter(|(_, challenge)| challenge.expiry <= ct)
+            .map(|(challenge_id, _)| *challenge_id)
+            .collect();
+        for challenge_id in expired {
+            challenge_write.remove(&challenge_id);
+        }
+        challenge_write.commit();
+
+        let mut session_write = self.sessions.write();
+        let expired: Vec<_> = session_write
+            .iter()
+            .filter(|(_, expiry)| **expiry <= ct)
+            .map(|(session_id, _)| *session_id)
+            .collect();
+        for session_id in expired {
+            session_write.remove(&session_id);
+        }
+        session_write.commit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+    use openssl::sign::Signer;
+
+    use super::{BreakGlass, BreakGlassCodeFile, BREAK_GLASS_SESSION_EXPIRY};
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[test]
+    fn test_idm_break_glass() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).expect("Invalid curve");
+        let key = EcKey::generate(&group)
+            .and_then(PKey::from_ec_key)
+            .expect("Unable to generate key");
+        let public_key_pem = key.public_key_to_pem().expect("Unable to export key");
+
+        let break_glass = BreakGlass::new(Some(&public_key_pem), None).expect("Unable to load key");
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let sign = |challenge: &[u8]| {
+            let mut signer =
+                Signer::new(MessageDigest::sha256(), &key).expect("Unable to create signer");
+            signer.update(challenge).expect("Unable to sign");
+            signer.sign_to_vec().expect("Unable to sign")
+        };
+
+        // A signature over a different challenge is rejected, and consumes the challenge.
+        let (challenge_id, _challenge) = break_glass.challenge(ct);
+        assert_eq!(
+            break_glass.verify_challenge(challenge_id, &sign(b"other"), ct),
+            Err(OperationError::NotAuthenticated)
+        );
+        let (challenge_id, challenge) = break_glass.challenge(ct);
+        assert_eq!(
+            break_glass.verify_challenge(challenge_id, &sign(&challenge), ct),
+            Ok(())
+        );
+        // The challenge can't be reused.
+        assert_eq!(
+            break_glass.verify_challenge(challenge_id, &sign(&challenge), ct),
+            Err(OperationError::InvalidSessionState)
+        );
+
+        let (uat, token) = break_glass
+            .issue(
+                UUID_ADMIN,
+                "admin@example.com".to_string(),
+                "Admin".to_string(),
+                ct,
+            )
+            .expect("Unable to issue session");
+        let verified = break_glass
+            .verify(&token, ct)
+            .expect("Unable to verify")
+            .expect("Token was not issued by break glass");
+        assert_eq!(verified.session_id, uat.session_id);
+        assert_eq!(verified.uuid, UUID_ADMIN);
+
+        let key_id = token.kid().expect("Token has no key id");
+        assert!(matches!(break_glass.public_jwk(key_id), Ok(Some(_))));
+        assert!(matches!(break_glass.public_jwk("other"), Ok(None)));
+
+        // The session expires.
+        let expired = ct + Duration::from_secs(BREAK_GLASS_SESSION_EXPIRY);
+        assert!(matches!(
+            break_glass.verify(&token, expired),
+            Err(OperationError::SessionExpired)
+        ));
+        break_glass.expire(expired);
+        assert!(matches!(
+            break_glass.verify(&token, expired),
+            Err(OperationError::NotAuthenticated)
+        ));
+
+        // Only ECDSA P-256 keys are accepted.
+        let group = EcGroup::from_curve_name(Nid::SECP384R1).expect("Invalid curve");
+        let key = EcKey::generate(&group)
+            .and_then(PKey::from_ec_key)
+            .expect("Unable to generate key");
+        let public_key_pem = key.public_key_to_pem().expect("Unable to export key");
+        assert!(BreakGlass::new(Some(&public_key_pem), None).is_err());
+    }
+
+    #[test]
+    fn test_idm_break_glass_codes() {
+        let path = std::env::temp_dir().join(format!("kanidm-break-glass-{}.json", Uuid::new_v4()));
+
+        let mut file = BreakGlassCodeFile::default();
+        let codes = file.generate("emergency", 2);
+        assert_eq!(codes.len(), 2);
+        file.write(&path).expect("Unable to write codes");
+
+        let break_glass = BreakGlass::new(None, Some(&path)).expect("Unable to load codes");
+        assert_eq!(
+            break_glass.emergency_accounts(),
+            vec![("emergency".to_string(), 2)]
+        );
+
+        // Challenges can't be used without a key.
+        assert!(!break_glass.has_key());
+
+        // The code must belong to the account.
+        assert_eq!(
+            break_glass.verify_code("other", &codes[0]),
+            Err(OperationError::NotAuthenticated)
+        );
+        assert_eq!(
+            break_glass.verify_code("emergency", "invalid"),
+            Err(OperationError::NotAuthenticated)
+        );
+
+        assert_eq!(break_glass.verify_code("emergency", &codes[0]), Ok(()));
+        // Each code can only be used once, even after a restart.
+        assert_eq!(
+            break_glass.verify_code("emergency", &codes[0]),
+            Err(OperationError::NotAuthenticated)
+        );
+        let break_glass = BreakGlass::new(None, Some(&path)).expect("Unable to load codes");
+        assert_eq!(
+            break_glass.verify_code("emergency", &codes[0]),
+            Err(OperationError::NotAuthenticated)
+        );
+        assert_eq!(break_glass.verify_code("emergency", &codes[1]), Ok(()));
+        assert_eq!(
+            break_glass.emergency_accounts(),
+            vec![("emergency".to_string(), 0)]
+        );
+
+        // Neither a key or codes.
+        assert!(BreakGlass::new(None, None).is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,250 @@
+//! Report the entries that have changed since a point in time. This allows clients to keep
+//! a cache of entries, and to only invalidate the entries that have changed rather than
+//! relying on expiry times.
+//!
+//! This is derived from the replication update vector, which tracks which entries were
+//! altered by each change. The cursor given to clients is the maximum change seen from
+//! each server in the topology.
+//!
+//! Only entries that the requester can read are reported. A deleted entry can only be read by
+//! those who can search the recycle bin, so when entries that the requester can't read have
+//! been deleted, the requester is told to reset instead. This way a cache never holds an entry
+//! that was deleted, without revealing which entry it was.
+
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use kanidm_proto::v1::ChangesResponse;
+
+use crate::be::BackendTransaction;
+use crate::idm::server::IdmServerProxyReadTransaction;
+use crate::prelude::*;
+use crate::repl::proto::ReplCidRange;
+use crate::repl::ruv::ReplicationUpdateVectorTransaction;
+
+fn encode_cursor(cursor: &BTreeMap<Uuid, Duration>) -> Result<String, OperationError> {
+    serde_json::to_vec(cursor)
+        .map(|data| URL_SAFE_NO_PAD.encode(data))
+        .map_err(|err| {
+            error!(?err, "Unable to serialise change cursor");
+            OperationError::SerdeJsonError
+        })
+}
+
+fn decode_cursor(cursor: &str) -> Result<BTreeMap<Uuid, Duration>, OperationError> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .ok_or_else(|| {
+            warn!("Invalid change cursor");
+            OperationError::InvalidRequestState
+        })
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    pub fn changes_since(
+        &mut self,
+        ident: &Identity,
+        cursor: Option<&str>,
+    ) -> Result<ChangesResponse, OperationError> {
+        let current_ranges = self.qs_read.get_be_txn().get_ruv().current_ruv_range()?;
+
+        let next_cursor = current_ranges
+            .iter()
+            .map(|(s_uuid, range)| (*s_uuid, range.ts_max))
+            .collect();
+        let next_cursor = encode_cursor(&next_cursor)?;
+
+        let reset = ChangesResponse {
+            cursor: next_cursor.clone(),
+            reset: true,
+            ..Default::default()
+        };
+
+        let Some(cursor) = cursor.map(decode_cursor).transpose()? else {
+            return Ok(reset);
+        };
+
+        let mut ranges = BTreeMap::new();
+        for (s_uuid, range) in current_ranges.iter() {
+            match cursor.get(s_uuid) {
+                // Changes between the cursor and the start of our range have been trimmed,
+                // so we can't know what has changed. The same is true for a server we
+                // haven't seen before.
+                Some(ts) if *ts < range.ts_min => return Ok(reset),
+                None => return Ok(reset),
+                Some(ts) if *ts < range.ts_max => {
+                    ranges.insert(
+                        *s_uuid,
+                        ReplCidRange {
+                            ts_min: *ts,
+                            ts_max: range.ts_max,
+                        },
+                    );
+                }
+                Some(_) => {}
+            }
+        }
+
+        if ranges.is_empty() {
+            return Ok(ChangesResponse {
+                cursor: next_cursor,
+                ..Default::default()
+            });
+        }
+
+        let entries = self.qs_read.get_be_txn().retrieve_range(&ranges)?;
+
+        let (live, deleted): (Vec<_>, Vec<_>) = entries
+            .iter()
+            .partition(|entry| entry.mask_recycled_ts().is_some());
+
+        // Only report changes to entries that the ident can read.
+        let changed = if live.is_empty() {
+            Vec::with_capacity(0)
+        } else {
+            let fc = f_or(
+                live.iter()
+                    .map(|entry| f_eq(Attribute::Uuid, PartialValue::Uuid(entry.get_uuid())))
+                    .collect(),
+            );
+            self.qs_read
+                .impersonate_search(filter!(fc.clone()), filter!(fc), ident)?
+                .iter()
+                .map(|entry| entry.get_uuid())
+                .collect()
+        };
+
+        let removed: Vec<Uuid> = if deleted.is_empty() {
+            Vec::with_capacity(0)
+        } else {
+            let fc = f_or(
+                deleted
+                    .iter()
+                    .map(|entry| f_eq(Attribute::Uuid, PartialValue::Uuid(entry.get_uuid())))
+                    .collect(),
+            );
+            self.qs_read
+                .impersonate_search(filter_rec!(fc.clone()), filter_rec!(fc), ident)?
+                .iter()
+                .map(|entry| entry.get_uuid())
+                .collect()
+        };
+
+        // Deleted entries that the ident can't read may still be held by its cache, and
+        // reporting them would reveal their uuids.
+        if removed.len() != deleted.len() {
+            debug!("Entries that can't be read were deleted, requesting a reset");
+            return Ok(reset);
+        }
+
+        Ok(ChangesResponse {
+            cursor: next_cursor,
+            reset: false,
+            changed,
+            removed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[idm_test]
+    async fn test_idm_changes_since(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let grp_uuid = Uuid::new_v4();
+
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        let e_grp = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Uuid, Value::Uuid(grp_uuid)),
+            (Attribute::Name, Value::new_iname("test_changes_group"))
+        );
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![e_grp])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let idm_admin = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("Unable to find idm_admin");
+        let ident = Identity::from_impersonate_entry_readwrite(idm_admin);
+        // Only system administrators can read deleted entries in the recycle bin.
+        let admin = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_ADMIN)
+            .expect("Unable to find admin");
+        let admin_ident = Identity::from_impersonate_entry_readwrite(admin);
+
+        // Without a cursor, we are told to reset.
+        let changes = idms_prox_read
+            .changes_since(&ident, None)
+            .expect("Failed to get changes");
+        assert!(changes.reset);
+        let cursor = changes.cursor;
+
+        // Nothing has changed yet.
+        let changes = idms_prox_read
+            .changes_since(&ident, Some(&cursor))
+            .expect("Failed to get changes");
+        assert!(!changes.reset);
+        assert!(changes.changed.is_empty());
+        assert_eq!(changes.cursor, cursor);
+
+        // An invalid cursor is rejected.
+        assert!(idms_prox_read
+            .changes_since(&ident, Some("invalid"))
+            .is_err());
+        drop(idms_prox_read);
+
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                grp_uuid,
+                &ModifyList::new_purge_and_set(Attribute::Description, Value::new_utf8s("changed"))
+            )
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let changes = idms_prox_read
+            .changes_since(&ident, Some(&cursor))
+            .expect("Failed to get changes");
+        assert!(!changes.reset);
+        assert_eq!(changes.changed, vec![grp_uuid]);
+        assert!(changes.removed.is_empty());
+        let cursor = changes.cursor;
+        drop(idms_prox_read);
+
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        assert!(idms_prox_write
+            .qs_write
+            .internal_delete_uuid(grp_uuid)
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let changes = idms_prox_read
+            .changes_since(&admin_ident, Some(&cursor))
+            .expect("Failed to get changes");
+        assert!(!changes.reset);
+        assert!(changes.changed.is_empty());
+        assert_eq!(changes.removed, vec![grp_uuid]);
+
+        // The uuid of a deleted entry isn't revealed to those who can't read it, and they
+        // are told to reset instead.
+        let changes = idms_prox_read
+            .changes_since(&ident, Some(&cursor))
+            .expect("Failed to get changes");
+        assert!(changes.reset);
+        assert!(changes.changed.is_empty());
+        assert!(changes.removed.is_empty());
+    }
+}
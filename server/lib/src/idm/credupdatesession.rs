@@ -8,9 +8,10 @@ use sshkey_attest::proto::PublicKey as SshPublicKey;
 
 use hashbrown::HashSet;
 use kanidm_proto::internal::{
-    CUCredState, CUExtPortal, CURegState, CURegWarning, CUStatus, CredentialDetail, PasskeyDetail,
-    PasswordFeedback, TotpSecret,
+    CUCredState, CUExtPortal, CUIntentTokenState, CUPasswordCheck, CURegState, CURegWarning,
+    CUStatus, CredentialDetail, PasskeyDetail, PasswordFeedback, TotpSecret,
 };
+use openssl::sha;
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use webauthn_rs::prelude::{
@@ -18,9 +19,14 @@ use webauthn_rs::prelude::{
     Passkey as PasskeyV4, PasskeyRegistration, RegisterPublicKeyCredential, WebauthnError,
 };
 
+use crate::credential::denylist::password_denylist_match;
+use crate::credential::history::{password_history_values, password_history_verify};
+use crate::credential::passphrase::passphrase_from_random;
 use crate::credential::totp::{Totp, TOTP_DEFAULT_STEP};
 use crate::credential::{BackupCodes, Credential};
 use crate::idm::account::Account;
+use crate::idm::audit::AuditEvent;
+use crate::idm::notification::SecurityNotification;
 use crate::idm::server::{IdmServerCredUpdateTransaction, IdmServerProxyWriteTransaction};
 use crate::prelude::*;
 use crate::server::access::Access;
@@ -40,12 +46,21 @@ const MINIMUM_INTENT_TTL: Duration = Duration::from_secs(300);
 const DEFAULT_INTENT_TTL: Duration = Duration::from_secs(3600);
 // Default 1 day.
 const MAXIMUM_INTENT_TTL: Duration = Duration::from_secs(86400);
+// An intent may be used to commit a credential update at most this many times.
+const MAXIMUM_INTENT_USES: u32 = 32;
+// The number of passphrases to try when suggesting one.
+const PASSPHRASE_SUGGEST_ATTEMPTS: usize = 8;
+// A session may check at most this many candidate passwords in each window.
+const PASSWORD_CHECK_LIMIT: u32 = 30;
+const PASSWORD_CHECK_WINDOW: Duration = Duration::from_secs(60);
 
 #[derive(Debug)]
 pub enum PasswordQuality {
     TooShort(u32),
     BadListed,
     DontReusePasswords,
+    DenyListed(String),
+    Breached,
     Feedback(Vec<PasswordFeedback>),
 }
 
@@ -53,6 +68,25 @@ pub enum PasswordQuality {
 pub struct CredentialUpdateIntentToken {
     pub intent_id: String,
     pub expiry_time: OffsetDateTime,
+    pub max_uses: u32,
+    // Who the intent was issued for, so that it can be delivered to them.
+    pub displayname: String,
+    pub spn: String,
+    pub mail_primary: Option<String>,
+}
+
+/// An intent that was issued for an account, identified by its reference.
+#[derive(Clone, Debug)]
+pub struct CredentialUpdateIntentInfo {
+    pub token_ref: String,
+    pub expiry_time: OffsetDateTime,
+    pub state: CUIntentTokenState,
+}
+
+/// Intent ids are secrets, so an intent is listed and revoked by a reference that is derived
+/// from its id instead.
+pub fn intent_token_ref(intent_id: &str) -> String {
+    hex::encode(&sha::sha256(intent_id.as_bytes())[..8])
 }
 
 #[derive(Clone, Debug)]
@@ -138,6 +172,8 @@ pub(crate) struct CredentialUpdateSession {
     resolved_account_policy: ResolvedAccountPolicy,
     // What intent was used to initiate this session.
     intent_token_id: Option<String>,
+    // If an intent was used, when it expires and how many uses it has left.
+    intent_expiry: Option<(Duration, u32)>,
 
     // Is there an extertal credential portal?
     ext_cred_portal: CUExtPortal,
@@ -164,6 +200,9 @@ pub(crate) struct CredentialUpdateSession {
 
     // Internal reg state of any inprogress totp or webauthn credentials.
     mfaregstate: MfaRegState,
+
+    // The start of the current password check window, and the checks made in it.
+    password_checks: (Duration, u32),
 }
 
 impl fmt::Debug for CredentialUpdateSession {
@@ -190,6 +229,7 @@ impl fmt::Debug for CredentialUpdateSession {
             .field("account.unix", &self.account.unix_extn().is_some())
             .field("resolved_account_policy", &self.resolved_account_policy)
             .field("intent_token_id", &self.intent_token_id)
+            .field("intent_expiry", &self.intent_expiry)
             .field("primary.detail()", &primary)
             .field("primary.state", &self.primary_state)
             .field("passkeys.list()", &passkeys)
@@ -306,6 +346,7 @@ pub enum CredentialUpdateSessionStatusWarnings {
     AttestedResidentKeyRequired,
     Unsatisfiable,
     WebauthnAttestationUnsatisfiable,
+    WebauthnAttestationNotTrusted,
     WebauthnUserVerificationRequired,
 }
 
@@ -330,6 +371,9 @@ impl From<CredentialUpdateSessionStatusWarnings> for CURegWarning {
             CredentialUpdateSessionStatusWarnings::WebauthnAttestationUnsatisfiable => {
                 CURegWarning::WebauthnAttestationUnsatisfiable
             }
+            CredentialUpdateSessionStatusWarnings::WebauthnAttestationNotTrusted => {
+                CURegWarning::WebauthnAttestationNotTrusted
+            }
             CredentialUpdateSessionStatusWarnings::WebauthnUserVerificationRequired => {
                 CURegWarning::WebauthnUserVerificationRequired
             }
@@ -361,6 +405,9 @@ pub struct CredentialUpdateSessionStatus {
 
     sshkeys: BTreeMap<String, SshPublicKey>,
     sshkeys_state: CredentialState,
+
+    intent_expiry_time: Option<OffsetDateTime>,
+    intent_uses_remaining: Option<u32>,
 }
 
 impl CredentialUpdateSessionStatus {
@@ -414,6 +461,8 @@ impl Into<CUStatus> for CredentialUpdateSessionStatus {
             unixcred_state: self.unixcred_state.into(),
             sshkeys: self.sshkeys,
             sshkeys_state: self.sshkeys_state.into(),
+            intent_expiry_time: self.intent_expiry_time,
+            intent_uses_remaining: self.intent_uses_remaining,
         }
     }
 }
@@ -469,6 +518,11 @@ impl From<&CredentialUpdateSession> for CredentialUpdateSessionStatus {
             sshkeys: session.sshkeys.clone(),
             sshkeys_state: session.sshkeys_state,
 
+            intent_expiry_time: session
+                .intent_expiry
+                .map(|(max_ttl, _)| OffsetDateTime::UNIX_EPOCH + max_ttl),
+            intent_uses_remaining: session.intent_expiry.map(|(_, uses)| uses),
+
             mfaregstate: match &session.mfaregstate {
                 MfaRegState::None => MfaRegStateStatus::None,
                 MfaRegState::TotpInit(token) => MfaRegStateStatus::TotpCheck(
@@ -497,6 +551,8 @@ pub struct InitCredentialUpdateIntentEvent {
     pub target: Uuid,
     // How long is it valid for?
     pub max_ttl: Option<Duration>,
+    // How many times may it be used?
+    pub max_uses: Option<u32>,
 }
 
 impl InitCredentialUpdateIntentEvent {
@@ -505,9 +561,15 @@ impl InitCredentialUpdateIntentEvent {
             ident,
             target,
             max_ttl,
+            max_uses: None,
         }
     }
 
+    pub fn with_max_uses(mut self, max_uses: Option<u32>) -> Self {
+        self.max_uses = max_uses;
+        self
+    }
+
     #[cfg(test)]
     pub fn new_impersonate_entry(
         e: std::sync::Arc<Entry<EntrySealed, EntryCommitted>>,
@@ -519,6 +581,34 @@ impl InitCredentialUpdateIntentEvent {
             ident,
             target,
             max_ttl: Some(max_ttl),
+            max_uses: None,
+        }
+    }
+}
+
+pub struct ListCredentialUpdateIntentEvent {
+    pub ident: Identity,
+    pub target: Uuid,
+}
+
+impl ListCredentialUpdateIntentEvent {
+    pub fn new(ident: Identity, target: Uuid) -> Self {
+        ListCredentialUpdateIntentEvent { ident, target }
+    }
+}
+
+pub struct RevokeCredentialUpdateIntentEvent {
+    pub ident: Identity,
+    pub target: Uuid,
+    pub token_ref: String,
+}
+
+impl RevokeCredentialUpdateIntentEvent {
+    pub fn new(ident: Identity, target: Uuid, token_ref: String) -> Self {
+        RevokeCredentialUpdateIntentEvent {
+            ident,
+            target,
+            token_ref,
         }
     }
 }
@@ -765,6 +855,7 @@ impl IdmServerProxyWriteTransaction<'_> {
         &mut self,
         sessionid: Uuid,
         intent_token_id: Option<String>,
+        intent_expiry: Option<(Duration, u32)>,
         account: Account,
         resolved_account_policy: ResolvedAccountPolicy,
         perms: CredUpdateSessionPerms,
@@ -813,10 +904,15 @@ impl IdmServerProxyWriteTransaction<'_> {
 
         let unixcred_state = if account.unix_extn().is_none() {
             CredentialState::PolicyDeny
-        } else if perms.unixcred_can_edit {
+        } else if !perms.unixcred_can_edit {
+            CredentialState::AccessDeny
+        } else if resolved_account_policy.allow_unix_password() {
             CredentialState::Modifiable
+        } else if account.unix_extn().and_then(|uext| uext.ucred()).is_some() {
+            // Policy denies unix passwords, but an existing one may still be removed.
+            CredentialState::DeleteOnly
         } else {
-            CredentialState::AccessDeny
+            CredentialState::PolicyDeny
         };
 
         let sshkeys_state = if perms.sshpubkey_can_edit {
@@ -838,8 +934,10 @@ impl IdmServerProxyWriteTransaction<'_> {
             BTreeMap::default()
         };
 
-        let unixcred: Option<Credential> = if matches!(unixcred_state, CredentialState::Modifiable)
-        {
+        let unixcred: Option<Credential> = if matches!(
+            unixcred_state,
+            CredentialState::Modifiable | CredentialState::DeleteOnly
+        ) {
             account.unix_extn().and_then(|uext| uext.ucred()).cloned()
         } else {
             None
@@ -906,6 +1004,7 @@ impl IdmServerProxyWriteTransaction<'_> {
             resolved_account_policy,
             issuer,
             intent_token_id,
+            intent_expiry,
             ext_cred_portal,
             primary,
             primary_state,
@@ -918,6 +1017,7 @@ impl IdmServerProxyWriteTransaction<'_> {
             attested_passkeys,
             attested_passkeys_state,
             mfaregstate: MfaRegState::None,
+            password_checks: (ct, 0),
         };
 
         let max_ttl = ct + MAXIMUM_CRED_UPDATE_TTL;
@@ -959,6 +1059,8 @@ impl IdmServerProxyWriteTransaction<'_> {
         event: &InitCredentialUpdateIntentEvent,
         ct: Duration,
     ) -> Result<CredentialUpdateIntentToken, OperationError> {
+        self.qs_write.check_maintenance_mode(&event.ident)?;
+
         let (account, _resolved_account_policy, perms) =
             self.validate_init_credential_update(event.target, &event.ident)?;
 
@@ -976,6 +1078,9 @@ impl IdmServerProxyWriteTransaction<'_> {
         // Absolute expiry of the intent token in epoch seconds
         let max_ttl = ct + clamped_mttl;
 
+        let max_uses = event.max_uses.unwrap_or(1).clamp(1, MAXIMUM_INTENT_USES);
+        debug!(?max_uses, "clamped update intent uses");
+
         // Get the expiry of the intent token as an odt.
         let expiry_time = OffsetDateTime::UNIX_EPOCH + max_ttl;
 
@@ -990,7 +1095,11 @@ impl IdmServerProxyWriteTransaction<'_> {
             Attribute::CredentialUpdateIntentToken,
             Value::IntentToken(
                 intent_id.clone(),
-                IntentTokenState::Valid { max_ttl, perms },
+                IntentTokenState::Valid {
+                    max_ttl,
+                    perms,
+                    uses_remaining: max_uses,
+                },
             ),
         );
 
@@ -1000,13 +1109,8 @@ impl IdmServerProxyWriteTransaction<'_> {
             .iter()
             .for_each(|(existing_intent_id, state)| {
                 let max_ttl = match state {
-                    IntentTokenState::Valid { max_ttl, perms: _ }
-                    | IntentTokenState::InProgress {
-                        max_ttl,
-                        perms: _,
-                        session_id: _,
-                        session_ttl: _,
-                    }
+                    IntentTokenState::Valid { max_ttl, .. }
+                    | IntentTokenState::InProgress { max_ttl, .. }
                     | IntentTokenState::Consumed { max_ttl } => *max_ttl,
                 };
 
@@ -1032,9 +1136,99 @@ impl IdmServerProxyWriteTransaction<'_> {
         Ok(CredentialUpdateIntentToken {
             intent_id,
             expiry_time,
+            max_uses,
+            displayname: account.displayname,
+            spn: account.spn,
+            mail_primary: account.mail_primary,
         })
     }
 
+    /// List the intents of an account that have not yet expired. This requires the same
+    /// permissions as issuing an intent.
+    #[instrument(level = "debug", skip_all)]
+    pub fn list_credential_update_intents(
+        &mut self,
+        event: &ListCredentialUpdateIntentEvent,
+        ct: Duration,
+    ) -> Result<Vec<CredentialUpdateIntentInfo>, OperationError> {
+        let (account, _resolved_account_policy, _perms) =
+            self.validate_init_credential_update(event.target, &event.ident)?;
+
+        let mut intents: Vec<_> = account
+            .credential_update_intent_tokens
+            .iter()
+            .filter_map(|(intent_id, state)| {
+                let (max_ttl, state) = match state {
+                    IntentTokenState::Valid {
+                        max_ttl,
+                        uses_remaining,
+                        ..
+                    } => (
+                        *max_ttl,
+                        CUIntentTokenState::Valid {
+                            uses_remaining: *uses_remaining,
+                        },
+                    ),
+                    IntentTokenState::InProgress {
+                        max_ttl,
+                        uses_remaining,
+                        ..
+                    } => (
+                        *max_ttl,
+                        CUIntentTokenState::InProgress {
+                            uses_remaining: *uses_remaining,
+                        },
+                    ),
+                    IntentTokenState::Consumed { max_ttl } => {
+                        (*max_ttl, CUIntentTokenState::Consumed)
+                    }
+                };
+
+                (ct < max_ttl).then(|| CredentialUpdateIntentInfo {
+                    token_ref: intent_token_ref(intent_id),
+                    expiry_time: OffsetDateTime::UNIX_EPOCH + max_ttl,
+                    state,
+                })
+            })
+            .collect();
+
+        intents.sort_by_key(|intent| intent.expiry_time);
+
+        Ok(intents)
+    }
+
+    /// Revoke an intent of an account so that it can no longer be used. A session that is in
+    /// progress with the intent will be unable to commit.
+    #[instrument(level = "debug", skip_all)]
+    pub fn revoke_credential_update_intent(
+        &mut self,
+        event: &RevokeCredentialUpdateIntentEvent,
+    ) -> Result<(), OperationError> {
+        let (account, _resolved_account_policy, _perms) =
+            self.validate_init_credential_update(event.target, &event.ident)?;
+
+        let intent_id = account
+            .credential_update_intent_tokens
+            .keys()
+            .find(|intent_id| intent_token_ref(intent_id) == event.token_ref)
+            .ok_or(OperationError::NoMatchingEntries)?;
+
+        let modlist = ModifyList::new_list(vec![Modify::Removed(
+            Attribute::CredentialUpdateIntentToken,
+            PartialValue::IntentToken(intent_id.clone()),
+        )]);
+
+        self.qs_write
+            .internal_modify(
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(account.uuid))),
+                &modlist,
+            )
+            .map_err(|e| {
+                request_error!(error = ?e);
+                e
+            })
+    }
+
     pub fn exchange_intent_credential_update(
         &mut self,
         token: CredentialUpdateIntentTokenExchange,
@@ -1042,6 +1236,8 @@ impl IdmServerProxyWriteTransaction<'_> {
     ) -> Result<(CredentialUpdateSessionToken, CredentialUpdateSessionStatus), OperationError> {
         let CredentialUpdateIntentTokenExchange { intent_id } = token;
 
+        self.qs_write.refuse_in_maintenance_mode()?;
+
         /*
             let entry = self.qs_write.internal_search_uuid(&token.target)?;
         */
@@ -1113,7 +1309,10 @@ impl IdmServerProxyWriteTransaction<'_> {
         // Check there is not already a user session in progress with this intent token.
         // Is there a need to revoke intent tokens?
 
-        let (max_ttl, perms) = match account.credential_update_intent_tokens.get(&intent_id) {
+        let (max_ttl, perms, uses_remaining) = match account
+            .credential_update_intent_tokens
+            .get(&intent_id)
+        {
             Some(IntentTokenState::Consumed { max_ttl: _ }) => {
                 security_info!(
                     %entry,
@@ -1125,6 +1324,7 @@ impl IdmServerProxyWriteTransaction<'_> {
             Some(IntentTokenState::InProgress {
                 max_ttl,
                 perms,
+                uses_remaining,
                 session_id,
                 session_ttl,
             }) => {
@@ -1151,9 +1351,13 @@ impl IdmServerProxyWriteTransaction<'_> {
                         "Initiating Update Session - Intent Token was in use {} - this will be invalidated.", session_id
                     );
                 };
-                (*max_ttl, *perms)
+                (*max_ttl, *perms, *uses_remaining)
             }
-            Some(IntentTokenState::Valid { max_ttl, perms }) => (*max_ttl, *perms),
+            Some(IntentTokenState::Valid {
+                max_ttl,
+                perms,
+                uses_remaining,
+            }) => (*max_ttl, *perms, *uses_remaining),
             None => {
                 admin_error!("Corruption may have occurred - index yielded an entry for intent_id, but the entry does not contain that intent_id");
                 return Err(OperationError::InvalidState);
@@ -1195,6 +1399,7 @@ impl IdmServerProxyWriteTransaction<'_> {
                 IntentTokenState::InProgress {
                     max_ttl,
                     perms,
+                    uses_remaining,
                     session_id,
                     session_ttl: current_time + MAXIMUM_CRED_UPDATE_TTL,
                 },
@@ -1218,6 +1423,7 @@ impl IdmServerProxyWriteTransaction<'_> {
         self.create_credupdate_session(
             session_id,
             Some(intent_id),
+            Some((max_ttl, uses_remaining)),
             account,
             resolved_account_policy,
             perms,
@@ -1231,6 +1437,8 @@ impl IdmServerProxyWriteTransaction<'_> {
         event: &InitCredentialUpdateEvent,
         current_time: Duration,
     ) -> Result<(CredentialUpdateSessionToken, CredentialUpdateSessionStatus), OperationError> {
+        self.qs_write.check_maintenance_mode(&event.ident)?;
+
         let (account, resolved_account_policy, perms) =
             self.validate_init_credential_update(event.target, &event.ident)?;
 
@@ -1243,6 +1451,7 @@ impl IdmServerProxyWriteTransaction<'_> {
         self.create_credupdate_session(
             sessionid,
             None,
+            None,
             account,
             resolved_account_policy,
             perms,
@@ -1320,6 +1529,8 @@ impl IdmServerProxyWriteTransaction<'_> {
         cust: &CredentialUpdateSessionToken,
         ct: Duration,
     ) -> Result<(), OperationError> {
+        self.qs_write.refuse_in_maintenance_mode()?;
+
         let (mut modlist, session, session_token) =
             self.credential_update_commit_common(cust, ct)?;
 
@@ -1354,25 +1565,30 @@ impl IdmServerProxyWriteTransaction<'_> {
             let entry = self.qs_write.internal_search_uuid(session.account.uuid)?;
             let account = Account::try_from_entry_rw(entry.as_ref(), &mut self.qs_write)?;
 
-            let max_ttl = match account.credential_update_intent_tokens.get(intent_token_id) {
+            let next_state = match account.credential_update_intent_tokens.get(intent_token_id) {
                 Some(IntentTokenState::InProgress {
                     max_ttl,
-                    perms: _,
+                    perms,
+                    uses_remaining,
                     session_id,
                     session_ttl: _,
                 }) => {
                     if *session_id != session_token.sessionid {
                         security_info!("Session originated from an intent token, but the intent token has initiated a conflicting second update session. Refusing to commit changes.");
                         return Err(OperationError::CU0005IntentTokenConflict);
+                    } else if *uses_remaining > 1 && ct < *max_ttl {
+                        // The intent may be used again, so return it to valid.
+                        IntentTokenState::Valid {
+                            max_ttl: *max_ttl,
+                            perms: *perms,
+                            uses_remaining: uses_remaining - 1,
+                        }
                     } else {
-                        *max_ttl
+                        IntentTokenState::Consumed { max_ttl: *max_ttl }
                     }
                 }
-                Some(IntentTokenState::Consumed { max_ttl: _ })
-                | Some(IntentTokenState::Valid {
-                    max_ttl: _,
-                    perms: _,
-                })
+                Some(IntentTokenState::Consumed { .. })
+                | Some(IntentTokenState::Valid { .. })
                 | None => {
                     security_info!("Session originated from an intent token, but the intent token has transitioned to an invalid state. Refusing to commit changes.");
                     return Err(OperationError::CU0006IntentTokenInvalidated);
@@ -1385,10 +1601,7 @@ impl IdmServerProxyWriteTransaction<'_> {
             ));
             modlist.push_mod(Modify::Present(
                 Attribute::CredentialUpdateIntentToken,
-                Value::IntentToken(
-                    intent_token_id.clone(),
-                    IntentTokenState::Consumed { max_ttl },
-                ),
+                Value::IntentToken(intent_token_id.clone(), next_state),
             ));
         };
 
@@ -1406,6 +1619,50 @@ impl IdmServerProxyWriteTransaction<'_> {
             CredentialState::AccessDeny => {}
         };
 
+        // Remember the password that is being replaced so that it can't be reused.
+        let pw_history_count = session.resolved_account_policy.pw_history_count();
+        let next_pw = match session.primary_state {
+            CredentialState::Modifiable => session
+                .primary
+                .as_ref()
+                .and_then(|cred| cred.password_ref().ok()),
+            _ => None,
+        };
+        if let Some(prev_pw) = session
+            .account
+            .primary()
+            .and_then(|cred| cred.password_ref().ok())
+        {
+            if pw_history_count > 0
+                && !matches!(session.primary_state, CredentialState::AccessDeny)
+                && next_pw != Some(prev_pw)
+            {
+                modlist.push_mod(Modify::Purged(Attribute::PasswordHistory));
+                password_history_values(
+                    Some(&session.account.password_history),
+                    pw_history_count,
+                    prev_pw,
+                )
+                .into_iter()
+                .for_each(|v| modlist.push_mod(Modify::Present(Attribute::PasswordHistory, v)));
+            }
+        }
+
+        // Record when the password changed, so that account policy can expire it.
+        let prev_pw = session
+            .account
+            .primary()
+            .and_then(|cred| cred.password_ref().ok());
+        if !matches!(session.primary_state, CredentialState::AccessDeny) && next_pw != prev_pw {
+            modlist.push_mod(Modify::Purged(Attribute::PasswordLastChanged));
+            if next_pw.is_some() {
+                modlist.push_mod(Modify::Present(
+                    Attribute::PasswordLastChanged,
+                    Value::new_datetime_epoch(ct),
+                ));
+            }
+        }
+
         match session.passkeys_state {
             CredentialState::DeleteOnly | CredentialState::Modifiable => {
                 modlist.push_mod(Modify::Purged(Attribute::PassKeys));
@@ -1489,7 +1746,45 @@ impl IdmServerProxyWriteTransaction<'_> {
                 .map_err(|e| {
                     request_error!(error = ?e);
                     e
-                })
+                })?;
+
+            self.queue_activity_event(AuditEvent::CredentialUpdated {
+                uuid: session.account.uuid,
+                spn: session.account.spn.clone(),
+                time: OffsetDateTime::UNIX_EPOCH + ct,
+            });
+
+            self.queue_notification(SecurityNotification::CredentialUpdated {
+                uuid: session.account.uuid,
+                time: OffsetDateTime::UNIX_EPOCH + ct,
+            });
+
+            // Any passkey that the account didn't have when this session began was enrolled
+            // during it, and the owner is told about each of them.
+            let new_passkeys = session
+                .passkeys
+                .iter()
+                .filter(|(uuid, _)| !session.account.passkeys.contains_key(uuid))
+                .map(|(_, (label, _))| label)
+                .chain(
+                    session
+                        .attested_passkeys
+                        .iter()
+                        .filter(|(uuid, _)| !session.account.attested_passkeys.contains_key(uuid))
+                        .map(|(_, (label, _))| label),
+                )
+                .cloned()
+                .collect::<Vec<_>>();
+
+            for label in new_passkeys {
+                self.queue_notification(SecurityNotification::PasskeyEnrolled {
+                    uuid: session.account.uuid,
+                    label,
+                    time: OffsetDateTime::UNIX_EPOCH + ct,
+                });
+            }
+
+            Ok(())
         }
     }
 
@@ -1506,13 +1801,14 @@ impl IdmServerProxyWriteTransaction<'_> {
             let entry = self.qs_write.internal_search_uuid(session.account.uuid)?;
             let account = Account::try_from_entry_rw(entry.as_ref(), &mut self.qs_write)?;
 
-            let (max_ttl, perms) = match account
+            let (max_ttl, perms, uses_remaining) = match account
                 .credential_update_intent_tokens
                 .get(intent_token_id)
             {
                 Some(IntentTokenState::InProgress {
                     max_ttl,
                     perms,
+                    uses_remaining,
                     session_id,
                     session_ttl: _,
                 }) => {
@@ -1520,14 +1816,11 @@ impl IdmServerProxyWriteTransaction<'_> {
                         security_info!("Session originated from an intent token, but the intent token has initiated a conflicting second update session. Refusing to commit changes.");
                         return Err(OperationError::InvalidState);
                     } else {
-                        (*max_ttl, *perms)
+                        (*max_ttl, *perms, *uses_remaining)
                     }
                 }
-                Some(IntentTokenState::Consumed { max_ttl: _ })
-                | Some(IntentTokenState::Valid {
-                    max_ttl: _,
-                    perms: _,
-                })
+                Some(IntentTokenState::Consumed { .. })
+                | Some(IntentTokenState::Valid { .. })
                 | None => {
                     security_info!("Session originated from an intent token, but the intent token has transitioned to an invalid state. Refusing to commit changes.");
                     return Err(OperationError::InvalidState);
@@ -1542,7 +1835,11 @@ impl IdmServerProxyWriteTransaction<'_> {
                 Attribute::CredentialUpdateIntentToken,
                 Value::IntentToken(
                     intent_token_id.clone(),
-                    IntentTokenState::Valid { max_ttl, perms },
+                    IntentTokenState::Valid {
+                        max_ttl,
+                        perms,
+                        uses_remaining,
+                    },
                 ),
             ));
         };
@@ -1662,6 +1959,26 @@ impl IdmServerCredUpdateTransaction<'_> {
             }
         }
 
+        // Check the terms the administrators have denied. This is done before zxcvbn so that
+        // the person is told exactly which term matched.
+        if let Some(term) = password_denylist_match(self.qs_read.pw_denylist(), cleartext) {
+            security_info!(?term, "Password contains a denied term, rejecting");
+            return Err(PasswordQuality::DenyListed(term.to_string()));
+        }
+
+        // If the policy requires it, check the dataset of breached passwords. A password that
+        // is known to attackers is weak no matter how random it appears to zxcvbn.
+        if resolved_account_policy.password_breach_check() {
+            if let Some(password_breach) = self.password_breach {
+                if password_breach.contains_password(cleartext) {
+                    security_info!("Password found in breached password dataset, rejecting");
+                    return Err(PasswordQuality::Breached);
+                }
+            } else {
+                warn!("Account policy requires a password breach check, but no breached password dataset is loaded");
+            }
+        }
+
         // does the password pass zxcvbn?
         let entropy = zxcvbn::zxcvbn(cleartext, related_inputs).map_err(|e| {
             admin_error!("zxcvbn check failure (password empty?) {:?}", e);
@@ -1804,6 +2121,51 @@ impl IdmServerCredUpdateTransaction<'_> {
         }
     }
 
+    /// Suggest a passphrase for the primary credential that satisfies the password policy of
+    /// this account. The passphrase is not stored, the person must still set it as their
+    /// password.
+    #[instrument(level = "trace", skip(cust, self))]
+    pub fn credential_primary_suggest_passphrase(
+        &self,
+        cust: &CredentialUpdateSessionToken,
+        ct: Duration,
+    ) -> Result<String, OperationError> {
+        let session_handle = self.get_current_session(cust, ct)?;
+        let session = session_handle.try_lock().map_err(|_| {
+            admin_error!("Session already locked, unable to proceed.");
+            OperationError::InvalidState
+        })?;
+        trace!(?session);
+
+        if !matches!(session.primary_state, CredentialState::Modifiable) {
+            error!("Session does not have permission to modify primary credential");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let pw_min_length = session.resolved_account_policy.pw_min_length();
+        let related_inputs = session.account.related_inputs();
+
+        // A passphrase may be rejected by chance, such as by containing a denied term, so
+        // we try a few before giving up.
+        for _ in 0..PASSPHRASE_SUGGEST_ATTEMPTS {
+            let passphrase = passphrase_from_random(pw_min_length as usize);
+            if self
+                .check_password_quality(
+                    &passphrase,
+                    &session.resolved_account_policy,
+                    related_inputs.as_slice(),
+                    session.account.radius_secret.as_deref(),
+                )
+                .is_ok()
+            {
+                return Ok(passphrase);
+            }
+        }
+
+        error!("Unable to suggest a passphrase that satisfies the password policy");
+        Err(OperationError::InvalidState)
+    }
+
     #[instrument(level = "trace", skip(cust, self))]
     pub fn credential_primary_set_password(
         &self,
@@ -1823,6 +2185,76 @@ impl IdmServerCredUpdateTransaction<'_> {
             return Err(OperationError::AccessDenied);
         };
 
+        self.check_primary_password(&session, pw)?;
+
+        let ncred = match &session.primary {
+            Some(primary) => {
+                // Is there a need to update the uuid of the cred re softlocks?
+                primary.set_password(self.crypto_policy, pw)?
+            }
+            None => Credential::new_password_only(self.crypto_policy, pw)?,
+        };
+
+        session.primary = Some(ncred);
+        Ok(session.deref().into())
+    }
+
+    /// Check a candidate password against the password policy of this account, without
+    /// setting it. This gives the same verdict as setting the password would, so that a
+    /// custom interface can give feedback while the password is typed.
+    #[instrument(level = "trace", skip(cust, self, pw))]
+    pub fn credential_primary_check_password(
+        &self,
+        cust: &CredentialUpdateSessionToken,
+        ct: Duration,
+        pw: &str,
+    ) -> Result<CUPasswordCheck, OperationError> {
+        let session_handle = self.get_current_session(cust, ct)?;
+        let mut session = session_handle.try_lock().map_err(|_| {
+            admin_error!("Session already locked, unable to proceed.");
+            OperationError::InvalidState
+        })?;
+        trace!(?session);
+
+        if !matches!(session.primary_state, CredentialState::Modifiable) {
+            error!("Session does not have permission to modify primary credential");
+            return Err(OperationError::AccessDenied);
+        };
+
+        // Limit how quickly candidates can be checked, as each one is checked against the
+        // password history of the account.
+        let (window_start, checks) = session.password_checks;
+        session.password_checks = if ct >= window_start + PASSWORD_CHECK_WINDOW {
+            (ct, 1)
+        } else if checks < PASSWORD_CHECK_LIMIT {
+            (window_start, checks + 1)
+        } else {
+            security_info!("Password check rate limit exceeded");
+            return Err(OperationError::CU0007PasswordCheckRateLimited);
+        };
+
+        let min_length = session.resolved_account_policy.pw_min_length();
+
+        match self.check_primary_password(&session, pw) {
+            Ok(()) => Ok(CUPasswordCheck {
+                acceptable: true,
+                min_length,
+                feedback: Vec::with_capacity(0),
+            }),
+            Err(OperationError::PasswordQuality(feedback)) => Ok(CUPasswordCheck {
+                acceptable: false,
+                min_length,
+                feedback,
+            }),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn check_primary_password(
+        &self,
+        session: &CredentialUpdateSession,
+        pw: &str,
+    ) -> Result<(), OperationError> {
         self.check_password_quality(
             pw,
             &session.resolved_account_policy,
@@ -1839,19 +2271,30 @@ impl IdmServerCredUpdateTransaction<'_> {
             PasswordQuality::DontReusePasswords => {
                 OperationError::PasswordQuality(vec![PasswordFeedback::DontReusePasswords])
             }
+            PasswordQuality::DenyListed(term) => {
+                OperationError::PasswordQuality(vec![PasswordFeedback::DenyListed(term)])
+            }
+            PasswordQuality::Breached => {
+                OperationError::PasswordQuality(vec![PasswordFeedback::Breached])
+            }
             PasswordQuality::Feedback(feedback) => OperationError::PasswordQuality(feedback),
         })?;
 
-        let ncred = match &session.primary {
-            Some(primary) => {
-                // Is there a need to update the uuid of the cred re softlocks?
-                primary.set_password(self.crypto_policy, pw)?
-            }
-            None => Credential::new_password_only(self.crypto_policy, pw)?,
-        };
+        let pw_history_count = session.resolved_account_policy.pw_history_count();
+        if pw_history_count > 0
+            && password_history_verify(
+                session.account.primary(),
+                Some(&session.account.password_history),
+                pw_history_count,
+                pw,
+            )?
+        {
+            return Err(OperationError::PasswordQuality(vec![
+                PasswordFeedback::DontReusePasswords,
+            ]));
+        }
 
-        session.primary = Some(ncred);
-        Ok(session.deref().into())
+        Ok(())
     }
 
     pub fn credential_primary_init_totp(
@@ -2346,26 +2789,37 @@ impl IdmServerCredUpdateTransaction<'_> {
             MfaRegState::AttestedPasskey(_ccr, pk_reg) => {
                 let result = self
                     .webauthn
-                    .finish_attested_passkey_registration(reg, pk_reg)
-                    .map_err(|e| {
-                        error!(eclass=?e, emsg=%e, "Unable to complete attested passkey registration");
-
-                        match e {
-                            WebauthnError::AttestationChainNotTrusted(_)
-                            | WebauthnError::AttestationNotVerifiable => {
-                                OperationError::CU0001WebauthnAttestationNotTrusted
-                            },
-                            WebauthnError::UserNotVerified => {
-                                OperationError::CU0003WebauthnUserNotVerified
-                            },
-                            _ => OperationError::CU0002WebauthnRegistrationError,
-                        }
-                    });
+                    .finish_attested_passkey_registration(reg, pk_reg);
 
                 // The reg is done. Clean up state before returning errors.
                 session.mfaregstate = MfaRegState::None;
 
-                let passkey = result?;
+                let passkey = match result {
+                    Ok(passkey) => passkey,
+                    // The authenticator isn't one that the account policy allows. This is
+                    // something the user can resolve by using a different device, so we
+                    // tell them rather than failing the session.
+                    Err(
+                        e @ (WebauthnError::AttestationChainNotTrusted(_)
+                        | WebauthnError::AttestationNotVerifiable),
+                    ) => {
+                        warn!(eclass=?e, emsg=%e, "Authenticator does not meet attestation policy");
+                        let mut cu_status: CredentialUpdateSessionStatus = session.deref().into();
+                        cu_status.append_ephemeral_warning(
+                            CredentialUpdateSessionStatusWarnings::WebauthnAttestationNotTrusted,
+                        );
+                        return Ok(cu_status);
+                    }
+                    Err(e) => {
+                        error!(eclass=?e, emsg=%e, "Unable to complete attested passkey registration");
+                        return Err(match e {
+                            WebauthnError::UserNotVerified => {
+                                OperationError::CU0003WebauthnUserNotVerified
+                            }
+                            _ => OperationError::CU0002WebauthnRegistrationError,
+                        });
+                    }
+                };
                 trace!(?passkey);
 
                 let pk_id = Uuid::new_v4();
@@ -2440,6 +2894,12 @@ impl IdmServerCredUpdateTransaction<'_> {
             PasswordQuality::DontReusePasswords => {
                 OperationError::PasswordQuality(vec![PasswordFeedback::DontReusePasswords])
             }
+            PasswordQuality::DenyListed(term) => {
+                OperationError::PasswordQuality(vec![PasswordFeedback::DenyListed(term)])
+            }
+            PasswordQuality::Breached => {
+                OperationError::PasswordQuality(vec![PasswordFeedback::Breached])
+            }
             PasswordQuality::Feedback(feedback) => OperationError::PasswordQuality(feedback),
         })?;
 
@@ -2493,8 +2953,8 @@ impl IdmServerCredUpdateTransaction<'_> {
         })?;
         trace!(?session);
 
-        if !matches!(session.unixcred_state, CredentialState::Modifiable) {
-            error!("Session does not have permission to modify unix credential");
+        if !matches!(session.sshkeys_state, CredentialState::Modifiable) {
+            error!("Session does not have permission to modify sshkeys");
             return Err(OperationError::AccessDenied);
         };
 
@@ -2514,6 +2974,14 @@ impl IdmServerCredUpdateTransaction<'_> {
             return Err(OperationError::DuplicateKey);
         }
 
+        if !session
+            .resolved_account_policy
+            .ssh_key_permitted(&sshpubkey)
+        {
+            error!("SSH Public Key is not permitted by the account policy");
+            return Err(OperationError::PL0003SshPublicKeyPolicyDenied);
+        }
+
         session.sshkeys.insert(label, sshpubkey);
 
         Ok(session.deref().into())
@@ -2572,8 +3040,12 @@ mod tests {
     use compact_jwt::JwsCompact;
     use std::time::Duration;
 
-    use kanidm_proto::internal::{CUExtPortal, CredentialDetailType, PasswordFeedback};
+    use kanidm_proto::internal::{
+        CUExtPortal, CUIntentTokenState, CredentialDetailType, PasswordFeedback,
+    };
     use kanidm_proto::v1::{AuthAllowed, AuthIssueSession, AuthMech, UnixUserToken};
+    use openssl::sha;
+    use time::OffsetDateTime;
     use uuid::uuid;
     use webauthn_authenticator_rs::softpasskey::SoftPasskey;
     use webauthn_authenticator_rs::softtoken::{self, SoftToken};
@@ -2581,10 +3053,13 @@ mod tests {
     use webauthn_rs::prelude::AttestationCaListBuilder;
 
     use super::{
-        CredentialState, CredentialUpdateSessionStatus, CredentialUpdateSessionStatusWarnings,
-        CredentialUpdateSessionToken, InitCredentialUpdateEvent, InitCredentialUpdateIntentEvent,
-        MfaRegStateStatus, MAXIMUM_CRED_UPDATE_TTL, MAXIMUM_INTENT_TTL, MINIMUM_INTENT_TTL,
+        intent_token_ref, CredentialState, CredentialUpdateSessionStatus,
+        CredentialUpdateSessionStatusWarnings, CredentialUpdateSessionToken,
+        InitCredentialUpdateEvent, InitCredentialUpdateIntentEvent,
+        ListCredentialUpdateIntentEvent, MfaRegStateStatus, RevokeCredentialUpdateIntentEvent,
+        MAXIMUM_CRED_UPDATE_TTL, MAXIMUM_INTENT_TTL, MAXIMUM_INTENT_USES, MINIMUM_INTENT_TTL,
     };
+    use crate::credential::breach::PasswordBreachFilter;
     use crate::credential::totp::Totp;
     use crate::event::CreateEvent;
     use crate::idm::audit::AuditEvent;
@@ -2592,6 +3067,7 @@ mod tests {
     use crate::idm::event::{
         AuthEvent, AuthResult, RegenerateRadiusSecretEvent, UnixUserAuthEvent,
     };
+    use crate::idm::notification::SecurityNotification;
     use crate::idm::server::{IdmServer, IdmServerCredUpdateTransaction, IdmServerDelayed};
     use crate::idm::AuthState;
     use crate::prelude::*;
@@ -2726,23 +3202,17 @@ mod tests {
         idms_prox_write.commit().expect("Failed to commit txn");
     }
 
-    async fn setup_test_session(
+    #[idm_test]
+    async fn credential_update_intent_multiple_uses(
         idms: &IdmServer,
-        ct: Duration,
-    ) -> (CredentialUpdateSessionToken, CredentialUpdateSessionStatus) {
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
         let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
 
-        // Remove the default all persons policy, it interferes with our test.
-        let modlist = ModifyList::new_purge(Attribute::CredentialTypeMinimum);
-        idms_prox_write
-            .qs_write
-            .internal_modify_uuid(UUID_IDM_ALL_PERSONS, &modlist)
-            .expect("Unable to change default session exp");
-
         let e2 = entry_init!(
             (Attribute::Class, EntryClass::Object.to_value()),
             (Attribute::Class, EntryClass::Account.to_value()),
-            (Attribute::Class, EntryClass::PosixAccount.to_value()),
             (Attribute::Class, EntryClass::Person.to_value()),
             (Attribute::Name, Value::new_iname("testperson")),
             (Attribute::Uuid, Value::Uuid(TESTPERSON_UUID)),
@@ -2751,10 +3221,220 @@ mod tests {
         );
 
         let ce = CreateEvent::new_internal(vec![e2]);
-        let cr = idms_prox_write.qs_write.create(&ce);
-        assert!(cr.is_ok());
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
 
-        let testperson = idms_prox_write
+        let idm_admin = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("failed");
+
+        // Excessive uses are clamped.
+        let intent_tok = idms_prox_write
+            .init_credential_update_intent(
+                &InitCredentialUpdateIntentEvent::new_impersonate_entry(
+                    idm_admin.clone(),
+                    TESTPERSON_UUID,
+                    MINIMUM_INTENT_TTL,
+                )
+                .with_max_uses(Some(u32::MAX)),
+                ct,
+            )
+            .expect("Failed to create intent token!");
+        assert_eq!(intent_tok.max_uses, MAXIMUM_INTENT_USES);
+
+        let intent_tok = idms_prox_write
+            .init_credential_update_intent(
+                &InitCredentialUpdateIntentEvent::new_impersonate_entry(
+                    idm_admin,
+                    TESTPERSON_UUID,
+                    MINIMUM_INTENT_TTL,
+                )
+                .with_max_uses(Some(2)),
+                ct,
+            )
+            .expect("Failed to create intent token!");
+        assert_eq!(intent_tok.max_uses, 2);
+
+        // First use - the session reports the remaining validity of the intent.
+        let (cust, c_status) = idms_prox_write
+            .exchange_intent_credential_update(intent_tok.clone().into(), ct)
+            .expect("Failed to exchange intent token");
+        assert_eq!(c_status.intent_uses_remaining, Some(2));
+        assert_eq!(
+            c_status.intent_expiry_time,
+            Some(OffsetDateTime::UNIX_EPOCH + ct + MINIMUM_INTENT_TTL)
+        );
+
+        // Cancelling doesn't consume a use.
+        idms_prox_write
+            .cancel_credential_update(&cust, ct)
+            .expect("Failed to cancel update");
+
+        let (cust, c_status) = idms_prox_write
+            .exchange_intent_credential_update(intent_tok.clone().into(), ct)
+            .expect("Failed to exchange intent token");
+        assert_eq!(c_status.intent_uses_remaining, Some(2));
+
+        idms_prox_write
+            .commit_credential_update(&cust, ct)
+            .expect("Failed to commit update");
+
+        // Second use
+        let (cust, c_status) = idms_prox_write
+            .exchange_intent_credential_update(intent_tok.clone().into(), ct)
+            .expect("Failed to exchange intent token");
+        assert_eq!(c_status.intent_uses_remaining, Some(1));
+
+        idms_prox_write
+            .commit_credential_update(&cust, ct)
+            .expect("Failed to commit update");
+
+        // The intent is now consumed.
+        let cur = idms_prox_write.exchange_intent_credential_update(intent_tok.into(), ct);
+        assert!(matches!(cur, Err(OperationError::SessionExpired)));
+
+        idms_prox_write.commit().expect("Failed to commit txn");
+    }
+
+    #[idm_test]
+    async fn credential_update_intent_list_revoke(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let e2 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(TESTPERSON_UUID)),
+            (Attribute::Description, Value::new_utf8s("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson")),
+            (
+                Attribute::Mail,
+                Value::EmailAddress("testperson@example.com".to_string(), true)
+            )
+        );
+
+        let ce = CreateEvent::new_internal(vec![e2]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+
+        let idm_admin = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("failed");
+
+        let intent_tok = idms_prox_write
+            .init_credential_update_intent(
+                &InitCredentialUpdateIntentEvent::new_impersonate_entry(
+                    idm_admin.clone(),
+                    TESTPERSON_UUID,
+                    MINIMUM_INTENT_TTL,
+                )
+                .with_max_uses(Some(3)),
+                ct,
+            )
+            .expect("Failed to create intent token!");
+        assert_eq!(
+            intent_tok.mail_primary.as_deref(),
+            Some("testperson@example.com")
+        );
+
+        let list_ev = ListCredentialUpdateIntentEvent::new(
+            Identity::from_impersonate_entry_readwrite(idm_admin.clone()),
+            TESTPERSON_UUID,
+        );
+
+        let intents = idms_prox_write
+            .list_credential_update_intents(&list_ev, ct)
+            .expect("Failed to list intents");
+        assert_eq!(intents.len(), 1);
+        assert_eq!(
+            intents[0].token_ref,
+            intent_token_ref(&intent_tok.intent_id)
+        );
+        assert!(matches!(
+            intents[0].state,
+            CUIntentTokenState::Valid { uses_remaining: 3 }
+        ));
+
+        // Expired intents are not listed.
+        let intents = idms_prox_write
+            .list_credential_update_intents(&list_ev, ct + MINIMUM_INTENT_TTL)
+            .expect("Failed to list intents");
+        assert!(intents.is_empty());
+
+        // An unknown reference can't be revoked.
+        let cur = idms_prox_write.revoke_credential_update_intent(
+            &RevokeCredentialUpdateIntentEvent::new(
+                Identity::from_impersonate_entry_readwrite(idm_admin.clone()),
+                TESTPERSON_UUID,
+                "0000000000000000".to_string(),
+            ),
+        );
+        assert!(matches!(cur, Err(OperationError::NoMatchingEntries)));
+
+        // A session in progress can't commit once the intent is revoked.
+        let (cust, _) = idms_prox_write
+            .exchange_intent_credential_update(intent_tok.clone().into(), ct)
+            .expect("Failed to exchange intent token");
+
+        idms_prox_write
+            .revoke_credential_update_intent(&RevokeCredentialUpdateIntentEvent::new(
+                Identity::from_impersonate_entry_readwrite(idm_admin),
+                TESTPERSON_UUID,
+                intent_token_ref(&intent_tok.intent_id),
+            ))
+            .expect("Failed to revoke intent");
+
+        let intents = idms_prox_write
+            .list_credential_update_intents(&list_ev, ct)
+            .expect("Failed to list intents");
+        assert!(intents.is_empty());
+
+        let cur = idms_prox_write.commit_credential_update(&cust, ct);
+        assert!(matches!(
+            cur,
+            Err(OperationError::CU0006IntentTokenInvalidated)
+        ));
+
+        let cur = idms_prox_write.exchange_intent_credential_update(intent_tok.into(), ct);
+        assert!(cur.is_err());
+
+        idms_prox_write.commit().expect("Failed to commit txn");
+    }
+
+    async fn setup_test_session(
+        idms: &IdmServer,
+        ct: Duration,
+    ) -> (CredentialUpdateSessionToken, CredentialUpdateSessionStatus) {
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        // Remove the default all persons policy, it interferes with our test.
+        let modlist = ModifyList::new_purge(Attribute::CredentialTypeMinimum);
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(UUID_IDM_ALL_PERSONS, &modlist)
+            .expect("Unable to change default session exp");
+
+        let e2 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::PosixAccount.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(TESTPERSON_UUID)),
+            (Attribute::Description, Value::new_utf8s("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson"))
+        );
+
+        let ce = CreateEvent::new_internal(vec![e2]);
+        let cr = idms_prox_write.qs_write.create(&ce);
+        assert!(cr.is_ok());
+
+        let testperson = idms_prox_write
             .qs_write
             .internal_search_uuid(TESTPERSON_UUID)
             .expect("failed");
@@ -3179,6 +3859,62 @@ mod tests {
             .is_none());
     }
 
+    #[idm_test]
+    async fn credential_update_security_notifications(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let mut notify_rx = idms
+            .notifications_subscribe()
+            .expect("Unable to subscribe to notifications");
+        // Only one subscriber is allowed.
+        assert!(idms.notifications_subscribe().is_err());
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw)
+            .expect("Failed to update the primary cred password");
+        assert!(c_status.can_commit);
+        drop(cutxn);
+
+        // A credential update that is never committed must not notify.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        idms_prox_write
+            .commit_credential_update(&cust, ct)
+            .expect("Failed to commit credential update.");
+        drop(idms_prox_write);
+        assert!(notify_rx.try_recv().is_err());
+
+        commit_session(idms, ct, cust).await;
+
+        match notify_rx.try_recv() {
+            Ok(SecurityNotification::CredentialUpdated { uuid, .. }) => {
+                assert_eq!(uuid, TESTPERSON_UUID);
+            }
+            _ => panic!("Oh no"),
+        }
+
+        // Authenticating with the new credential notifies of the session.
+        assert!(check_testperson_password(idms, idms_delayed, test_pw, ct)
+            .await
+            .is_some());
+
+        match notify_rx.try_recv() {
+            Ok(SecurityNotification::SessionCreated { uuid, source, .. }) => {
+                assert_eq!(uuid, TESTPERSON_UUID);
+                assert_eq!(source, Source::Internal);
+            }
+            _ => panic!("Oh no"),
+        }
+
+        assert!(notify_rx.try_recv().is_err());
+    }
+
     #[idm_test]
     async fn credential_update_password_quality_checks(
         idms: &IdmServer,
@@ -3273,6 +4009,275 @@ mod tests {
         drop(cutxn);
     }
 
+    #[idm_test]
+    async fn credential_update_suggest_passphrase(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (cust, _) = setup_test_session(idms, ct).await;
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+
+        let passphrase = cutxn
+            .credential_primary_suggest_passphrase(&cust, ct)
+            .expect("Failed to suggest a passphrase");
+        assert!(passphrase.len() >= PW_MIN_LENGTH as usize);
+
+        // The suggestion satisfies the password policy.
+        let c_status = cutxn
+            .credential_primary_set_password(&cust, ct, &passphrase)
+            .expect("Failed to set the suggested passphrase");
+        assert!(c_status.can_commit);
+
+        drop(cutxn);
+    }
+
+    #[idm_test]
+    async fn credential_update_check_password(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (cust, _) = setup_test_session(idms, ct).await;
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+
+        // The verdict matches what setting the password would give.
+        let check = cutxn
+            .credential_primary_check_password(&cust, ct, "test")
+            .expect("Failed to check password");
+        assert!(!check.acceptable);
+        assert_eq!(check.min_length, PW_MIN_LENGTH);
+        assert_eq!(
+            check.feedback,
+            vec![PasswordFeedback::TooShort(PW_MIN_LENGTH)]
+        );
+
+        let check = cutxn
+            .credential_primary_check_password(&cust, ct, "password1234")
+            .expect("Failed to check password");
+        assert!(!check.acceptable);
+        assert_eq!(
+            check.feedback,
+            vec![
+                PasswordFeedback::AddAnotherWordOrTwo,
+                PasswordFeedback::ThisIsACommonPassword,
+            ]
+        );
+
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        let check = cutxn
+            .credential_primary_check_password(&cust, ct, test_pw)
+            .expect("Failed to check password");
+        assert!(check.acceptable);
+        assert!(check.feedback.is_empty());
+
+        // Checking a password does not set it.
+        let c_status = cutxn
+            .credential_update_status(&cust, ct)
+            .expect("Failed to get the current session status.");
+        assert!(c_status.primary.is_none());
+
+        // Checks are rate limited within a window.
+        for _ in 3..PASSWORD_CHECK_LIMIT {
+            assert!(cutxn
+                .credential_primary_check_password(&cust, ct, test_pw)
+                .is_ok());
+        }
+        assert!(matches!(
+            cutxn.credential_primary_check_password(&cust, ct, test_pw),
+            Err(OperationError::CU0007PasswordCheckRateLimited)
+        ));
+
+        let ct = ct + PASSWORD_CHECK_WINDOW;
+        assert!(cutxn
+            .credential_primary_check_password(&cust, ct, test_pw)
+            .is_ok());
+
+        drop(cutxn);
+    }
+
+    #[idm_test]
+    async fn credential_update_password_denylist(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (cust, _) = setup_test_session(idms, ct).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let modlist = ModifyList::new_list(vec![
+            Modify::Present(Attribute::DeniedPasswordTerm, Value::new_iutf8("Acme")),
+            Modify::Present(Attribute::DeniedPasswordTerm, Value::new_iutf8("summer")),
+        ]);
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(UUID_SYSTEM_CONFIG, &modlist)
+            .expect("Unable to set the password denylist");
+        assert!(idms_prox_write.commit().is_ok());
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+
+        // The matched term is reported, including through case and substitutions.
+        let err = cutxn
+            .credential_primary_set_password(&cust, ct, "ohnahT8ahg4Ahz-ACME-eeBoh0oozaiquaeN2ru")
+            .unwrap_err();
+        assert!(
+            matches!(err, OperationError::PasswordQuality(details) if details == vec!(PasswordFeedback::DenyListed("acme".to_string())))
+        );
+
+        let err = cutxn
+            .credential_primary_set_password(&cust, ct, "ohnahT8ahg4Ahz-$umm3r-eeBoh0oozaiquaeN2ru")
+            .unwrap_err();
+        assert!(
+            matches!(err, OperationError::PasswordQuality(details) if details == vec!(PasswordFeedback::DenyListed("summer".to_string())))
+        );
+
+        // Other passwords are unaffected.
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        assert!(cutxn
+            .credential_primary_set_password(&cust, ct, test_pw)
+            .is_ok());
+
+        drop(cutxn);
+    }
+
+    #[idm_test]
+    async fn credential_update_password_breach_check(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let breached_pw = "ohnahT8ahg4Ahzeehaiphier9eBoh0oozaiquaeN2ru";
+
+        let mut filter = PasswordBreachFilter::new(100, 0.001);
+        filter.insert_sha1(&sha::sha1(breached_pw.as_bytes()));
+        assert!(idms.password_breach_enable(filter).is_ok());
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let modlist =
+            ModifyList::new_purge_and_set(Attribute::PasswordBreachCheck, Value::Bool(true));
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(UUID_IDM_ALL_ACCOUNTS, &modlist)
+            .expect("Unable to enable the password breach check");
+        assert!(idms_prox_write.commit().is_ok());
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+
+        let err = cutxn
+            .credential_primary_set_password(&cust, ct, breached_pw)
+            .unwrap_err();
+        assert!(
+            matches!(err, OperationError::PasswordQuality(details) if details == vec!(PasswordFeedback::Breached))
+        );
+
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        assert!(cutxn
+            .credential_primary_set_password(&cust, ct, test_pw)
+            .is_ok());
+
+        drop(cutxn);
+    }
+
+    #[idm_test]
+    async fn credential_update_password_maximum_age(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let max_age = Duration::from_secs(86400 * 90);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let modlist = ModifyList::new_purge_and_set(
+            Attribute::AuthPasswordMaximumAge,
+            Value::Uint32(max_age.as_secs() as u32),
+        );
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(UUID_IDM_ALL_ACCOUNTS, &modlist)
+            .expect("Unable to set the password maximum age");
+        assert!(idms_prox_write.commit().is_ok());
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        assert!(cutxn
+            .credential_primary_set_password(&cust, ct, test_pw)
+            .is_ok());
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Within the maximum age the password is fine.
+        assert!(check_testperson_password(idms, idms_delayed, test_pw, ct)
+            .await
+            .is_some());
+
+        // Once it has expired, no session is issued.
+        let expired_ct = ct + max_age;
+        let mut idms_auth = idms.auth().await.unwrap();
+        let AuthResult { sessionid, .. } = idms_auth
+            .auth(
+                &AuthEvent::named_init("testperson"),
+                expired_ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to init auth");
+        let AuthResult { sessionid, .. } = idms_auth
+            .auth(
+                &AuthEvent::begin_mech(sessionid, AuthMech::Password),
+                expired_ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to begin auth");
+        let AuthResult { state, .. } = idms_auth
+            .auth(
+                &AuthEvent::cred_step_password(sessionid, test_pw),
+                expired_ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to step auth");
+        idms_auth.commit().expect("Must not fail");
+
+        let AuthState::CredentialUpdateRequired(token) = state else {
+            panic!("Expected the password to be expired, got {state:?}");
+        };
+        assert!(idms_delayed.try_recv().is_err());
+
+        // The token begins a credential update session to change the password.
+        let mut idms_prox_write = idms.proxy_write(expired_ct).await.unwrap();
+        let (cust, _) = idms_prox_write
+            .password_expired_exchange(&token, Source::Internal, expired_ct)
+            .expect("Failed to exchange the password expired token");
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let next_pw = "Ohb3phaeroo4ienahzaezee0ahngee6eeHepo3aiquuekah2eikee1eeng4naeT";
+        assert!(cutxn
+            .credential_primary_set_password(&cust, expired_ct, next_pw)
+            .is_ok());
+        drop(cutxn);
+        commit_session(idms, expired_ct, cust).await;
+
+        assert!(
+            check_testperson_password(idms, idms_delayed, next_pw, expired_ct)
+                .await
+                .is_some()
+        );
+
+        // Once the password is changed the token can't be used again.
+        let mut idms_prox_write = idms.proxy_write(expired_ct).await.unwrap();
+        assert!(matches!(
+            idms_prox_write.password_expired_exchange(&token, Source::Internal, expired_ct),
+            Err(OperationError::SessionExpired)
+        ));
+    }
+
     #[idm_test]
     async fn credential_update_password_min_length_account_policy(
         idms: &IdmServer,
@@ -3344,6 +4349,97 @@ mod tests {
         commit_session(idms, ct, cust).await;
     }
 
+    #[idm_test]
+    async fn credential_update_password_history_account_policy(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let modlist =
+            ModifyList::new_purge_and_set(Attribute::AuthPasswordHistoryCount, Value::Uint32(2));
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(UUID_IDM_ALL_ACCOUNTS, &modlist)
+            .expect("Unable to change password history count");
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let pws: Vec<String> = (0..4).map(|_| password_from_random_len(20)).collect();
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        cutxn
+            .credential_primary_set_password(&cust, ct, &pws[0])
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // The current password can't be reused.
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let err = cutxn
+            .credential_primary_set_password(&cust, ct, &pws[0])
+            .unwrap_err();
+        assert!(
+            matches!(err, OperationError::PasswordQuality(details) if details == vec!(PasswordFeedback::DontReusePasswords,))
+        );
+        cutxn
+            .credential_primary_set_password(&cust, ct, &pws[1])
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        cutxn
+            .credential_primary_set_password(&cust, ct, &pws[2])
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Both previous passwords, and the current one, are rejected.
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        for pw in pws[0..3].iter() {
+            let err = cutxn
+                .credential_primary_set_password(&cust, ct, pw)
+                .unwrap_err();
+            assert!(
+                matches!(err, OperationError::PasswordQuality(details) if details == vec!(PasswordFeedback::DontReusePasswords,))
+            );
+        }
+        cutxn
+            .credential_primary_set_password(&cust, ct, &pws[3])
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Only the two most recent previous passwords are kept, so the oldest can be used
+        // again.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let entry = idms_prox_read
+            .qs_read
+            .internal_search_uuid(TESTPERSON_UUID)
+            .expect("Failed to get testperson");
+        assert_eq!(
+            entry
+                .get_ava_as_credential_map(Attribute::PasswordHistory)
+                .map(|history| history.len()),
+            Some(2)
+        );
+        drop(idms_prox_read);
+
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        cutxn
+            .credential_primary_set_password(&cust, ct, &pws[0])
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+    }
+
     // Test set of primary account password
     //    - fail pw quality checks etc
     //    - set correctly.
@@ -3867,6 +4963,57 @@ mod tests {
         );
     }
 
+    #[idm_test]
+    async fn credential_update_passkey_enrolled_notification(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let mut notify_rx = idms
+            .notifications_subscribe()
+            .expect("Unable to subscribe to notifications");
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let origin = cutxn.get_origin().clone();
+
+        let mut wa = WebauthnAuthenticator::new(SoftPasskey::new(true));
+        let c_status = create_new_passkey(ct, &origin, &cutxn, &cust, &mut wa).await;
+        let pk_uuid = c_status.passkeys.first().map(|pkd| pkd.uuid).unwrap();
+
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        assert!(matches!(
+            notify_rx.try_recv(),
+            Ok(SecurityNotification::CredentialUpdated { uuid, .. }) if uuid == TESTPERSON_UUID
+        ));
+        match notify_rx.try_recv() {
+            Ok(SecurityNotification::PasskeyEnrolled { uuid, label, .. }) => {
+                assert_eq!(uuid, TESTPERSON_UUID);
+                assert_eq!(label, "softtoken");
+            }
+            _ => panic!("Oh no"),
+        }
+        assert!(notify_rx.try_recv().is_err());
+
+        // Removing the passkey is a credential change, but not an enrolment.
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        cutxn
+            .credential_passkey_remove(&cust, ct, pk_uuid)
+            .expect("Failed to delete the passkey");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        assert!(matches!(
+            notify_rx.try_recv(),
+            Ok(SecurityNotification::CredentialUpdated { .. })
+        ));
+        assert!(notify_rx.try_recv().is_err());
+    }
+
     #[idm_test]
     async fn credential_update_access_denied(
         idms: &IdmServer,
@@ -4309,14 +5456,14 @@ mod tests {
 
         // Finish the registration
         let label = "softtoken".to_string();
-        let err = cutxn
+        let c_status = cutxn
             .credential_attested_passkey_finish(&cust, ct, label, &passkey_resp)
-            .unwrap_err();
+            .expect("Failed to finish attested passkey registration");
 
-        assert!(matches!(
-            err,
-            OperationError::CU0001WebauthnAttestationNotTrusted
-        ));
+        assert!(c_status
+            .warnings
+            .contains(&CredentialUpdateSessionStatusWarnings::WebauthnAttestationNotTrusted));
+        assert!(c_status.attested_passkeys.is_empty());
 
         // -------------------------------------------------------
         // Reject a credential with wrong CA / correct aaguid
@@ -4336,14 +5483,14 @@ mod tests {
 
         // Finish the registration
         let label = "softtoken".to_string();
-        let err = cutxn
+        let c_status = cutxn
             .credential_attested_passkey_finish(&cust, ct, label, &passkey_resp)
-            .unwrap_err();
+            .expect("Failed to finish attested passkey registration");
 
-        assert!(matches!(
-            err,
-            OperationError::CU0001WebauthnAttestationNotTrusted
-        ));
+        assert!(c_status
+            .warnings
+            .contains(&CredentialUpdateSessionStatusWarnings::WebauthnAttestationNotTrusted));
+        assert!(c_status.attested_passkeys.is_empty());
 
         // -------------------------------------------------------
         // Accept credential with correct CA/aaguid
@@ -4746,6 +5893,65 @@ mod tests {
             .is_none());
     }
 
+    #[idm_test]
+    async fn credential_update_unix_password_policy_deny(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        cutxn
+            .credential_unix_set_password(&cust, ct, test_pw)
+            .expect("Failed to update the unix cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Now deny unix passwords to all persons.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_ALL_PERSONS,
+                &ModifyList::new_purge_and_set(
+                    Attribute::AllowUnixPassword,
+                    Value::new_bool(false),
+                ),
+            )
+            .expect("Unable to deny unix passwords");
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        // The existing password can only be removed.
+        let (cust, c_status) = renew_test_session(idms, ct).await;
+        assert!(matches!(
+            c_status.unixcred_state,
+            CredentialState::DeleteOnly
+        ));
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let err = cutxn
+            .credential_unix_set_password(&cust, ct, test_pw)
+            .unwrap_err();
+        assert_eq!(err, OperationError::AccessDenied);
+
+        let c_status = cutxn
+            .credential_unix_delete(&cust, ct)
+            .expect("Failed to delete the unix cred");
+        assert!(c_status.unixcred.is_none());
+
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Once removed, a unix password can't be set at all.
+        let (_cust, c_status) = renew_test_session(idms, ct).await;
+        assert!(matches!(
+            c_status.unixcred_state,
+            CredentialState::PolicyDeny
+        ));
+    }
+
     #[idm_test]
     async fn credential_update_sshkeys(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
         let sshkey_valid_1 =
@@ -1,5 +1,5 @@
 use crate::prelude::*;
-use crate::value::AuthType;
+use crate::value::{AuthType, CredentialFactor};
 use time::OffsetDateTime;
 use uuid::Uuid;
 use webauthn_rs::prelude::AuthenticationResult;
@@ -13,6 +13,8 @@ pub enum DelayedAction {
     WebauthnCounterIncrement(WebauthnCounterIncrement),
     BackupCodeRemoval(BackupCodeRemoval),
     AuthSessionRecord(AuthSessionRecord),
+    ApiTokenUse(ApiTokenUse),
+    SessionUse(SessionUse),
 }
 
 pub struct PasswordUpgrade {
@@ -64,4 +66,26 @@ pub struct AuthSessionRecord {
     pub issued_by: IdentityId,
     pub scope: SessionScope,
     pub type_: AuthType,
+    // The factors of the credential that were used, to record their usage.
+    pub factors: Vec<CredentialFactor>,
+    pub source: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Record that an api token was used to authenticate.
+#[derive(Debug)]
+pub struct ApiTokenUse {
+    pub target_uuid: Uuid,
+    pub token_id: Uuid,
+    pub used_at: OffsetDateTime,
+    pub source: Option<String>,
+}
+
+/// Record that an auth session was used.
+#[derive(Debug)]
+pub struct SessionUse {
+    pub target_uuid: Uuid,
+    pub session_id: Uuid,
+    pub used_at: OffsetDateTime,
+    pub source: Option<String>,
 }
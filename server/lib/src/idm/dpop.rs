@@ -0,0 +1,306 @@
+//! Demonstration of proof of possession (DPoP) binds OAuth2 tokens to a key held by the client,
+//! so that a stolen token is useless without that key. The client signs a short lived proof
+//! for each request, and tokens issued in response to a proof carry the thumbprint of the key
+//! in their `cnf` claim. Requests that use a bound token must then be accompanied by a proof
+//! from the same key.
+//!
+//! ref <https://www.rfc-editor.org/rfc/rfc9449>
+
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use compact_jwt::{Jwk, JwsCompact, JwsEs256Verifier, JwsVerifier};
+use kanidm_proto::oauth2::OAuth2TokenConfirmation;
+use openssl::sha;
+use serde::{Deserialize, Serialize};
+
+use crate::idm::oauth2::Oauth2Error;
+use crate::prelude::*;
+
+const DPOP_PROOF_TYP: &str = "dpop+jwt";
+
+/// The only proof algorithm we accept. This matches the default signing algorithm of our
+/// own tokens.
+pub(crate) const DPOP_PROOF_ALG: &str = "ES256";
+
+/// A DPoP proof and the request it was sent with.
+#[derive(Debug, Clone)]
+pub struct DPoPProof {
+    /// The compact serialised proof, which is a jwt signed by the client.
+    pub proof: String,
+    /// The http method of the request.
+    pub method: String,
+    /// The path of the request, which is combined with our origin to check the proof.
+    pub path: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DPoPProofHeader {
+    pub(crate) typ: Option<String>,
+    pub(crate) alg: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DPoPProofClaims {
+    jti: String,
+    htm: String,
+    htu: Url,
+    iat: i64,
+    /// The hash of the access token, required when a token is presented with the proof.
+    ath: Option<String>,
+}
+
+impl DPoPProof {
+    /// Verify that this proof was created for this request, returning the confirmation of the
+    /// key that signed it. If an access token is presented with the proof, the proof must
+    /// contain the hash of that token.
+    pub(crate) fn verify(
+        &self,
+        origin: &Url,
+        access_token: Option<&str>,
+        ct: Duration,
+    ) -> Result<OAuth2TokenConfirmation, Oauth2Error> {
+        let proof = JwsCompact::from_str(&self.proof).map_err(|err| {
+            security_info!(?err, "DPoP proof is not a valid jws");
+            Oauth2Error::InvalidDpopProof
+        })?;
+
+        // The header is checked before the signature, as the signature algorithm is selected
+        // from it.
+        let header = self
+            .proof
+            .split('.')
+            .next()
+            .and_then(|header| URL_SAFE_NO_PAD.decode(header).ok())
+            .and_then(|header| serde_json::from_slice::<DPoPProofHeader>(&header).ok())
+            .ok_or_else(|| {
+                security_info!("DPoP proof header is invalid");
+                Oauth2Error::InvalidDpopProof
+            })?;
+
+        if header.typ.as_deref() != Some(DPOP_PROOF_TYP) || header.alg != DPOP_PROOF_ALG {
+            security_info!(typ = ?header.typ, alg = %header.alg, "DPoP proof type or algorithm is not supported");
+            return Err(Oauth2Error::InvalidDpopProof);
+        }
+
+        let jwk = proof.get_jwk_pubkey().ok_or_else(|| {
+            security_info!("DPoP proof does not contain a public key");
+            Oauth2Error::InvalidDpopProof
+        })?;
+
+        let claims: DPoPProofClaims = JwsEs256Verifier::try_from(jwk)
+            .and_then(|verifier| verifier.verify(&proof))
+            .and_then(|jws| jws.from_json())
+            .map_err(|err| {
+                security_info!(?err, "DPoP proof signature is invalid");
+                Oauth2Error::InvalidDpopProof
+            })?;
+
+        if claims.jti.is_empty() {
+            security_info!("DPoP proof does not contain a jti");
+            return Err(Oauth2Error::InvalidDpopProof);
+        }
+
+        if claims.htm != self.method {
+            security_info!(htm = %claims.htm, method = %self.method, "DPoP proof was created for a different method");
+            return Err(Oauth2Error::InvalidDpopProof);
+        }
+
+        // The query and fragment are not part of the comparison.
+        let mut htu = claims.htu;
+        htu.set_query(None);
+        htu.set_fragment(None);
+
+        let expected_htu = origin.join(&self.path).map_err(|err| {
+            error!(?err, "Unable to build the request url for a DPoP proof");
+            Oauth2Error::ServerError(OperationError::InvalidState)
+        })?;
+
+        if htu != expected_htu {
+            security_info!(%htu, %expected_htu, "DPoP proof was created for a different url");
+            return Err(Oauth2Error::InvalidDpopProof);
+        }
+
+        // Proofs are only valid for a short time, which limits how long a captured proof
+        // could be replayed for.
+        let max_age = DPOP_PROOF_MAX_AGE as i64;
+        let now = ct.as_secs() as i64;
+        if claims.iat < now - max_age || claims.iat > now + max_age {
+            security_info!(iat = %claims.iat, %now, "DPoP proof is not within its validity window");
+            return Err(Oauth2Error::InvalidDpopProof);
+        }
+
+        if let Some(access_token) = access_token {
+            let ath = URL_SAFE_NO_PAD.encode(sha::sha256(access_token.as_bytes()));
+            if claims.ath.as_deref() != Some(ath.as_str()) {
+                security_info!("DPoP proof was created for a different access token");
+                return Err(Oauth2Error::InvalidDpopProof);
+            }
+        }
+
+        jwk_thumbprint(jwk).map(|jkt| OAuth2TokenConfirmation {
+            jkt: Some(jkt),
+            x5t_s256: None,
+        })
+    }
+}
+
+/// The thumbprint of an EC public key. The required members are hashed in lexicographic order
+/// without whitespace, so that the same key always has the same thumbprint.
+/// ref <https://www.rfc-editor.org/rfc/rfc7638#section-3>
+pub(crate) fn jwk_thumbprint(jwk: &Jwk) -> Result<String, Oauth2Error> {
+    let value = serde_json::to_value(jwk).map_err(|err| {
+        error!(?err, "Unable to serialise DPoP public key");
+        Oauth2Error::ServerError(OperationError::SerdeJsonError)
+    })?;
+
+    let member = |name: &str| value.get(name).and_then(|v| v.as_str());
+
+    let (Some("EC"), Some(crv), Some(x), Some(y)) =
+        (member("kty"), member("crv"), member("x"), member("y"))
+    else {
+        security_info!("DPoP public key is not an EC key");
+        return Err(Oauth2Error::InvalidDpopProof);
+    };
+
+    let canonical = format!(r#"{{"crv":"{crv}","kty":"EC","x":"{x}","y":"{y}"}}"#);
+
+    Ok(URL_SAFE_NO_PAD.encode(sha::sha256(canonical.as_bytes())))
+}
+
+/// Create a DPoP proof as a client would, returning the proof and the thumbprint of its key.
+#[cfg(test)]
+pub(crate) fn test_dpop_proof(
+    signer: &compact_jwt::JwsEs256Signer,
+    method: &str,
+    htu: &str,
+    access_token: Option<&str>,
+    ct: Duration,
+) -> (String, String) {
+    use compact_jwt::{jws::JwsBuilder, JwsSigner};
+
+    let claims = DPoPProofClaims {
+        jti: Uuid::new_v4().to_string(),
+        htm: method.to_string(),
+        htu: Url::parse(htu).expect("Invalid htu"),
+        iat: ct.as_secs() as i64,
+        ath: access_token.map(|token| URL_SAFE_NO_PAD.encode(sha::sha256(token.as_bytes()))),
+    };
+
+    let jws = JwsBuilder::into_json(&claims)
+        .map(|builder| builder.set_typ(Some(DPOP_PROOF_TYP)).build())
+        .expect("Unable to build proof");
+
+    let proof = signer.sign(&jws).expect("Unable to sign proof");
+
+    let jwk = signer
+        .public_key_as_jwk()
+        .expect("Unable to get public key");
+
+    (
+        proof.to_string(),
+        jwk_thumbprint(&jwk).expect("Unable to create thumbprint"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_dpop_proof, DPoPProof};
+    use crate::idm::oauth2::Oauth2Error;
+    use crate::prelude::*;
+    use compact_jwt::JwsEs256Signer;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    fn test_signer() -> JwsEs256Signer {
+        JwsEs256Signer::generate_es256()
+            .expect("Unable to create signer")
+            .set_sign_option_embed_jwk(true)
+    }
+
+    #[test]
+    fn test_dpop_proof_verify() {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let origin = Url::parse("https://idm.example.com").expect("Invalid origin");
+        let signer = test_signer();
+
+        let (proof, jkt) = test_dpop_proof(
+            &signer,
+            "POST",
+            "https://idm.example.com/oauth2/token?ignored=1",
+            None,
+            ct,
+        );
+
+        let dpop = DPoPProof {
+            proof,
+            method: "POST".to_string(),
+            path: "/oauth2/token".to_string(),
+        };
+
+        let cnf = dpop
+            .verify(&origin, None, ct)
+            .expect("Proof should be valid");
+        assert_eq!(cnf.jkt, Some(jkt.clone()));
+
+        // Same key, same thumbprint.
+        let (_, jkt_again) = test_dpop_proof(&signer, "GET", "https://a.example.com", None, ct);
+        assert_eq!(jkt, jkt_again);
+
+        // Wrong method
+        let wrong_method = DPoPProof {
+            method: "GET".to_string(),
+            ..dpop.clone()
+        };
+        assert_eq!(
+            wrong_method.verify(&origin, None, ct),
+            Err(Oauth2Error::InvalidDpopProof)
+        );
+
+        // Wrong path
+        let wrong_path = DPoPProof {
+            path: "/oauth2/token/introspect".to_string(),
+            ..dpop.clone()
+        };
+        assert_eq!(
+            wrong_path.verify(&origin, None, ct),
+            Err(Oauth2Error::InvalidDpopProof)
+        );
+
+        // Too old
+        let later = ct + Duration::from_secs(DPOP_PROOF_MAX_AGE + 1);
+        assert_eq!(
+            dpop.verify(&origin, None, later),
+            Err(Oauth2Error::InvalidDpopProof)
+        );
+    }
+
+    #[test]
+    fn test_dpop_proof_access_token_hash() {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let origin = Url::parse("https://idm.example.com").expect("Invalid origin");
+        let signer = test_signer();
+        let url = "https://idm.example.com/oauth2/openid/test/userinfo";
+
+        let (proof, _) = test_dpop_proof(&signer, "GET", url, Some("token"), ct);
+        let dpop = DPoPProof {
+            proof,
+            method: "GET".to_string(),
+            path: "/oauth2/openid/test/userinfo".to_string(),
+        };
+
+        assert!(dpop.verify(&origin, Some("token"), ct).is_ok());
+        assert_eq!(
+            dpop.verify(&origin, Some("other token"), ct),
+            Err(Oauth2Error::InvalidDpopProof)
+        );
+
+        // A proof without a token hash can't be used with a token.
+        let (proof, _) = test_dpop_proof(&signer, "GET", url, None, ct);
+        let dpop = DPoPProof { proof, ..dpop };
+        assert_eq!(
+            dpop.verify(&origin, Some("token"), ct),
+            Err(Oauth2Error::InvalidDpopProof)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Email link authentication sends a short lived link to the primary mail address of an
+//! account. Opening the link in the browser that began the authentication completes it.
+//!
+//! The link contains a token that is signed by the domain key, and is held by the
+//! authentication session that requested it. The token can only be presented once, and
+//! only to that session. This must be allowed by the account policy, and is only offered
+//! when mail can be sent.
+
+use std::time::Duration;
+
+use compact_jwt::Jws;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::idm::account::Account;
+use crate::prelude::*;
+use crate::server::keys::KeyObject;
+
+/// How long an email link is valid for after it is sent, in seconds.
+pub const EMAIL_LINK_EXPIRY: u64 = 600;
+
+/// The content of the message that must be sent to the person who is authenticating.
+#[derive(Debug, Clone)]
+pub struct EmailLinkMessage {
+    pub mail: String,
+    pub displayname: String,
+    pub spn: String,
+    /// The token to present to complete the authentication.
+    pub token: String,
+    pub expiry: OffsetDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EmailLinkClaims {
+    sub: Uuid,
+    nonce: Uuid,
+    exp: i64,
+}
+
+/// Sign a new email link token for the account. Returns the message to send, and the time the
+/// token expires.
+pub(crate) fn email_link_issue(
+    account: &Account,
+    mail: &str,
+    key_object: &KeyObject,
+    ct: Duration,
+) -> Result<(EmailLinkMessage, Duration), OperationError> {
+    let expiry = ct + Duration::from_secs(EMAIL_LINK_EXPIRY);
+
+    let claims = EmailLinkClaims {
+        sub: account.uuid,
+        nonce: Uuid::new_v4(),
+        exp: expiry.as_secs() as i64,
+    };
+
+    let jws = Jws::into_json(&claims).map_err(|err| {
+        error!(?err, "Failed to serialise email link");
+        OperationError::AU0002JwsSerialisation
+    })?;
+
+    let token = key_object.jws_es256_sign(&jws, ct).map_err(|err| {
+        error!(?err, "Failed to sign email link");
+        OperationError::AU0003JwsSignature
+    })?;
+
+    let message = EmailLinkMessage {
+        mail: mail.to_string(),
+        displayname: account.displayname.clone(),
+        spn: account.spn.clone(),
+        token: token.to_string(),
+        expiry: OffsetDateTime::UNIX_EPOCH + expiry,
+    };
+
+    Ok((message, expiry))
+}
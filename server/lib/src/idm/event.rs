@@ -95,6 +95,42 @@ impl RegenerateRadiusSecretEvent {
     }
 }
 
+#[derive(Debug)]
+pub struct RadiusCertificateIssueEvent {
+    pub ident: Identity,
+    pub target: Uuid,
+    pub csr: String,
+}
+
+impl RadiusCertificateIssueEvent {
+    pub fn from_parts(
+        ident: Identity,
+        target: Uuid,
+        csr: String,
+    ) -> Result<Self, OperationError> {
+        Ok(RadiusCertificateIssueEvent { ident, target, csr })
+    }
+
+    #[cfg(test)]
+    pub fn new_internal(target: Uuid, csr: String) -> Self {
+        let ident = Identity::from_internal();
+
+        RadiusCertificateIssueEvent { ident, target, csr }
+    }
+}
+
+#[derive(Debug)]
+pub struct SshCertificateIssueEvent {
+    pub ident: Identity,
+    pub public_key: String,
+}
+
+impl SshCertificateIssueEvent {
+    pub fn from_parts(ident: Identity, public_key: String) -> Result<Self, OperationError> {
+        Ok(SshCertificateIssueEvent { ident, public_key })
+    }
+}
+
 #[derive(Debug)]
 pub struct RadiusAuthTokenEvent {
     pub ident: Identity,
@@ -225,6 +261,21 @@ impl CredentialStatusEvent {
     }
 }
 
+#[derive(Debug)]
+pub struct ReadAccountPolicyEvent {
+    pub ident: Identity,
+    pub target: Uuid,
+}
+
+impl ReadAccountPolicyEvent {
+    #[cfg(test)]
+    pub fn new_internal(target: Uuid) -> Self {
+        let ident = Identity::from_internal();
+
+        ReadAccountPolicyEvent { ident, target }
+    }
+}
+
 #[derive(Debug)]
 pub struct ReadBackupCodeEvent {
     pub ident: Identity,
@@ -425,6 +476,22 @@ impl AuthEventStep {
             cred: AuthCredential::Passkey(Box::new(passkey_response)),
         })
     }
+
+    #[cfg(test)]
+    pub fn cred_step_email_link(sid: Uuid, token: &str) -> Self {
+        AuthEventStep::Cred(AuthEventStepCred {
+            sessionid: sid,
+            cred: AuthCredential::EmailLink(token.to_string()),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn cred_step_federated(sid: Uuid, state: &str) -> Self {
+        AuthEventStep::Cred(AuthEventStepCred {
+            sessionid: sid,
+            cred: AuthCredential::Federated(state.to_string()),
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -505,6 +572,22 @@ impl AuthEvent {
             step: AuthEventStep::cred_step_passkey(sid, passkey_response),
         }
     }
+
+    #[cfg(test)]
+    pub fn cred_step_email_link(sid: Uuid, token: &str) -> Self {
+        AuthEvent {
+            ident: None,
+            step: AuthEventStep::cred_step_email_link(sid, token),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn cred_step_federated(sid: Uuid, state: &str) -> Self {
+        AuthEvent {
+            ident: None,
+            step: AuthEventStep::cred_step_federated(sid, state),
+        }
+    }
 }
 
 // Probably should be a struct with the session id present.
@@ -0,0 +1,914 @@
+//! Lookup of the country and network that an address belongs to, from databases in the
+//! MaxMind DB format such as GeoLite2 or DB-IP Lite. The databases are read locally, so
+//! addresses are never sent to another service.
+//!
+//! The locations that each account has signed in from are remembered so that unusual sign
+//! ins can be flagged. These are a sign in from a country the account has not used before, or
+//! from a location that is too far from the previous sign in to have travelled between them.
+//! This history is held in memory by each server, so it is lost when the server restarts.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::server::identity::Source;
+
+const METADATA_MARKER: &[u8] = b"\xab\xcd\xefMaxMind.com";
+// The metadata is always within the last 128KiB of the database.
+const METADATA_MAX_SIZE: usize = 128 * 1024;
+// The search tree is followed by 16 zero bytes before the data section.
+const DATA_SECTION_SEPARATOR: usize = 16;
+// Guard against a corrupt database that nests or points to values without end.
+const MAX_DECODE_DEPTH: usize = 16;
+
+/// The location of an address is only approximate, so sign ins closer than this to the
+/// previous location are never considered impossible travel.
+const MIN_TRAVEL_DISTANCE_KM: f64 = 500.0;
+const EARTH_RADIUS_KM: f64 = 6371.0;
+/// The most countries that are remembered for each account.
+const MAX_KNOWN_COUNTRIES: usize = 32;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GeoIpError {
+    InvalidMetadata,
+    InvalidTree,
+    InvalidData,
+}
+
+/// A value from the data section of a database. Bytes, arrays and booleans are not used by
+/// the lookups, so they are decoded but not kept.
+#[derive(Debug, Clone, PartialEq)]
+enum MmdbValue {
+    String(String),
+    Double(f64),
+    Uint(u128),
+    Int(i32),
+    Map(BTreeMap<String, MmdbValue>),
+    Float(f32),
+    Other,
+}
+
+impl MmdbValue {
+    fn get(&self, key: &str) -> Option<&MmdbValue> {
+        match self {
+            MmdbValue::Map(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            MmdbValue::String(value) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            MmdbValue::Double(value) => Some(*value),
+            MmdbValue::Float(value) => Some(f64::from(*value)),
+            _ => None,
+        }
+    }
+
+    fn as_u32(&self) -> Option<u32> {
+        match self {
+            MmdbValue::Uint(value) => u32::try_from(*value).ok(),
+            MmdbValue::Int(value) => u32::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+}
+
+fn be_uint(bytes: &[u8]) -> u128 {
+    bytes
+        .iter()
+        .fold(0, |acc, byte| (acc << 8) | u128::from(*byte))
+}
+
+fn be_usize(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |acc, byte| (acc << 8) | usize::from(*byte))
+}
+
+/// Decodes values from a section of the database. Pointers are relative to the start of
+/// the section.
+struct Decoder<'a> {
+    section: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&[u8], GeoIpError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.section.get(offset..end))
+            .ok_or(GeoIpError::InvalidData)
+    }
+
+    fn byte(&self, offset: usize) -> Result<u8, GeoIpError> {
+        self.section
+            .get(offset)
+            .copied()
+            .ok_or(GeoIpError::InvalidData)
+    }
+
+    /// Decode the value at this offset, returning it and the offset that follows it.
+    fn decode(&self, offset: usize, depth: usize) -> Result<(MmdbValue, usize), GeoIpError> {
+        if depth > MAX_DECODE_DEPTH {
+            return Err(GeoIpError::InvalidData);
+        }
+
+        let ctrl = self.byte(offset)?;
+        let mut offset = offset + 1;
+        let mut data_type = ctrl >> 5;
+
+        if data_type == 1 {
+            let size = usize::from((ctrl >> 3) & 0x3);
+            let value = usize::from(ctrl & 0x7);
+            let bytes = self.bytes(offset, size + 1)?;
+            let pointer = match size {
+                0 => (value << 8) | be_usize(bytes),
+                1 => ((value << 16) | be_usize(bytes)) + 2048,
+                2 => ((value << 24) | be_usize(bytes)) + 526336,
+                _ => be_usize(bytes),
+            };
+            let (value, _) = self.decode(pointer, depth + 1)?;
+            return Ok((value, offset + size + 1));
+        }
+
+        if data_type == 0 {
+            data_type = self
+                .byte(offset)?
+                .checked_add(7)
+                .ok_or(GeoIpError::InvalidData)?;
+            offset += 1;
+        }
+
+        let (size, offset) = match ctrl & 0x1f {
+            29 => (29 + be_usize(self.bytes(offset, 1)?), offset + 1),
+            30 => (285 + be_usize(self.bytes(offset, 2)?), offset + 2),
+            31 => (65821 + be_usize(self.bytes(offset, 3)?), offset + 3),
+            size => (usize::from(size), offset),
+        };
+
+        match data_type {
+            2 => {
+                let value = std::str::from_utf8(self.bytes(offset, size)?)
+                    .map_err(|_| GeoIpError::InvalidData)?;
+                Ok((MmdbValue::String(value.to_string()), offset + size))
+            }
+            3 if size == 8 => {
+                let mut value = [0; 8];
+                value.copy_from_slice(self.bytes(offset, size)?);
+                Ok((MmdbValue::Double(f64::from_be_bytes(value)), offset + size))
+            }
+            4 => self
+                .bytes(offset, size)
+                .map(|_| (MmdbValue::Other, offset + size)),
+            5 | 6 | 9 | 10 if size <= 16 => Ok((
+                MmdbValue::Uint(be_uint(self.bytes(offset, size)?)),
+                offset + size,
+            )),
+            7 => {
+                let mut map = BTreeMap::new();
+                let mut offset = offset;
+                for _ in 0..size {
+                    let (key, next) = self.decode(offset, depth + 1)?;
+                    let MmdbValue::String(key) = key else {
+                        return Err(GeoIpError::InvalidData);
+                    };
+                    let (value, next) = self.decode(next, depth + 1)?;
+                    map.insert(key, value);
+                    offset = next;
+                }
+                Ok((MmdbValue::Map(map), offset))
+            }
+            8 if size <= 4 => Ok((
+                MmdbValue::Int(be_uint(self.bytes(offset, size)?) as u32 as i32),
+                offset + size,
+            )),
+            11 => {
+                let mut offset = offset;
+                for _ in 0..size {
+                    let (_, next) = self.decode(offset, depth + 1)?;
+                    offset = next;
+                }
+                Ok((MmdbValue::Other, offset))
+            }
+            14 => Ok((MmdbValue::Other, offset)),
+            15 if size == 4 => {
+                let mut value = [0; 4];
+                value.copy_from_slice(self.bytes(offset, size)?);
+                Ok((MmdbValue::Float(f32::from_be_bytes(value)), offset + size))
+            }
+            _ => Err(GeoIpError::InvalidData),
+        }
+    }
+}
+
+/// A database in the MaxMind DB format, which maps networks to the data about them.
+pub struct GeoIpDatabase {
+    data: Vec<u8>,
+    database_type: String,
+    node_count: u32,
+    record_size: u16,
+    ip_version: u16,
+    /// The node that the IPv4 networks begin at within an IPv6 database.
+    ipv4_start: u32,
+    data_start: usize,
+    data_end: usize,
+}
+
+impl fmt::Debug for GeoIpDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeoIpDatabase")
+            .field("database_type", &self.database_type)
+            .field("node_count", &self.node_count)
+            .field("record_size", &self.record_size)
+            .field("ip_version", &self.ip_version)
+            .finish()
+    }
+}
+
+impl GeoIpDatabase {
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, GeoIpError> {
+        let search_start = data.len().saturating_sub(METADATA_MAX_SIZE);
+        let data_end = data[search_start..]
+            .windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .map(|position| search_start + position)
+            .ok_or(GeoIpError::InvalidMetadata)?;
+
+        let decoder = Decoder {
+            section: &data[data_end + METADATA_MARKER.len()..],
+        };
+        let (metadata, _) = decoder
+            .decode(0, 0)
+            .map_err(|_| GeoIpError::InvalidMetadata)?;
+
+        let node_count = metadata
+            .get("node_count")
+            .and_then(MmdbValue::as_u32)
+            .ok_or(GeoIpError::InvalidMetadata)?;
+        let record_size = metadata
+            .get("record_size")
+            .and_then(MmdbValue::as_u32)
+            .and_then(|size| u16::try_from(size).ok())
+            .filter(|size| [24, 28, 32].contains(size))
+            .ok_or(GeoIpError::InvalidMetadata)?;
+        let ip_version = metadata
+            .get("ip_version")
+            .and_then(MmdbValue::as_u32)
+            .and_then(|version| u16::try_from(version).ok())
+            .filter(|version| [4, 6].contains(version))
+            .ok_or(GeoIpError::InvalidMetadata)?;
+        let database_type = metadata
+            .get("database_type")
+            .and_then(MmdbValue::as_str)
+            .unwrap_or_default()
+            .to_string();
+
+        // Each node holds two records.
+        let tree_size = node_count as usize * usize::from(record_size) / 4;
+        let data_start = tree_size + DATA_SECTION_SEPARATOR;
+        if data_start > data_end {
+            return Err(GeoIpError::InvalidTree);
+        }
+
+        let mut db = GeoIpDatabase {
+            data,
+            database_type,
+            node_count,
+            record_size,
+            ip_version,
+            ipv4_start: 0,
+            data_start,
+            data_end,
+        };
+
+        if ip_version == 6 {
+            // IPv4 addresses are stored as ::a.b.c.d, which is 96 zero bits into the tree.
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= db.node_count {
+                    break;
+                }
+                node = db.record(node, false)?;
+            }
+            db.ipv4_start = node;
+        }
+
+        Ok(db)
+    }
+
+    /// The type of the database, such as `GeoLite2-City` or `GeoLite2-ASN`.
+    pub fn database_type(&self) -> &str {
+        &self.database_type
+    }
+
+    fn record(&self, node: u32, right: bool) -> Result<u32, GeoIpError> {
+        let node_size = usize::from(self.record_size) / 4;
+        let start = node as usize * node_size;
+        let bytes = self
+            .data
+            .get(start..start + node_size)
+            .ok_or(GeoIpError::InvalidTree)?;
+
+        let record = match (self.record_size, right) {
+            (24, false) => be_usize(&bytes[0..3]),
+            (24, true) => be_usize(&bytes[3..6]),
+            (28, false) => ((usize::from(bytes[3]) & 0xf0) << 20) | be_usize(&bytes[0..3]),
+            (28, true) => ((usize::from(bytes[3]) & 0x0f) << 24) | be_usize(&bytes[4..7]),
+            (_, false) => be_usize(&bytes[0..4]),
+            (_, true) => be_usize(&bytes[4..8]),
+        };
+
+        u32::try_from(record).map_err(|_| GeoIpError::InvalidTree)
+    }
+
+    /// The data of the most specific network that contains this address, if any.
+    fn lookup(&self, ip: IpAddr) -> Result<Option<MmdbValue>, GeoIpError> {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(v6)),
+            ip => ip,
+        };
+
+        let (address, bit_count, mut node) = match ip {
+            IpAddr::V4(v4) => (u128::from(u32::from(v4)), 32, self.ipv4_start),
+            IpAddr::V6(v6) if self.ip_version == 6 => (u128::from(v6), 128, 0),
+            // An IPv4 database can't contain IPv6 networks.
+            IpAddr::V6(_) => return Ok(None),
+        };
+
+        for bit in (0..bit_count).rev() {
+            if node >= self.node_count {
+                break;
+            }
+            node = self.record(node, (address >> bit) & 1 == 1)?;
+        }
+
+        if node == self.node_count {
+            return Ok(None);
+        }
+
+        let offset = node
+            .checked_sub(self.node_count)
+            .and_then(|offset| (offset as usize).checked_sub(DATA_SECTION_SEPARATOR))
+            .ok_or(GeoIpError::InvalidTree)?;
+
+        let decoder = Decoder {
+            section: &self.data[self.data_start..self.data_end],
+        };
+        decoder.decode(offset, 0).map(|(value, _)| Some(value))
+    }
+}
+
+/// Where an address is located. This is included in audit events and notifications.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoLocation {
+    /// The ISO 3166 code of the country.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// The autonomous system number of the network.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asn: Option<u32>,
+    /// The organisation that operates the network.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asn_org: Option<String>,
+}
+
+impl fmt::Display for GeoLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::with_capacity(2);
+        if let Some(country) = &self.country {
+            parts.push(country.clone());
+        }
+        match (self.asn, &self.asn_org) {
+            (Some(asn), Some(org)) => parts.push(format!("AS{asn} {org}")),
+            (Some(asn), None) => parts.push(format!("AS{asn}")),
+            (None, Some(org)) => parts.push(org.clone()),
+            (None, None) => {}
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// Why a sign in was considered unusual.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SessionAnomaly {
+    /// The account signed in from a country it has not signed in from before.
+    NewCountry { country: String },
+    /// The account signed in from a location that is too far from its previous sign in to
+    /// have travelled between them in the time since.
+    ImpossibleTravel {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        previous_country: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        country: Option<String>,
+        distance_km: u64,
+        elapsed_secs: u64,
+    },
+}
+
+impl fmt::Display for SessionAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionAnomaly::NewCountry { country } => {
+                write!(f, "the first sign in from the country {country}")
+            }
+            SessionAnomaly::ImpossibleTravel {
+                previous_country,
+                country,
+                distance_km,
+                elapsed_secs,
+            } => write!(
+                f,
+                "a sign in from {} that is {} km from the previous sign in from {} only {} minutes earlier",
+                country.as_deref().unwrap_or("an unknown country"),
+                distance_km,
+                previous_country.as_deref().unwrap_or("an unknown country"),
+                elapsed_secs / 60
+            ),
+        }
+    }
+}
+
+/// The location of an address, including its coordinates. The coordinates are only used to
+/// estimate travel, and are not recorded anywhere.
+struct Located {
+    location: GeoLocation,
+    coordinates: Option<(f64, f64)>,
+}
+
+struct LastSignIn {
+    country: Option<String>,
+    coordinates: (f64, f64),
+    time: Duration,
+}
+
+#[derive(Default)]
+struct LocationHistory {
+    countries: BTreeSet<String>,
+    last: Option<LastSignIn>,
+}
+
+/// The great circle distance between two coordinates in kilometres.
+fn distance_km((lat_a, lon_a): (f64, f64), (lat_b, lon_b): (f64, f64)) -> f64 {
+    let d_lat = (lat_b - lat_a).to_radians();
+    let d_lon = (lon_b - lon_a).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2)
+        + lat_a.to_radians().cos() * lat_b.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().min(1.0).asin()
+}
+
+pub struct GeoIp {
+    location_db: GeoIpDatabase,
+    asn_db: Option<GeoIpDatabase>,
+    max_travel_speed_kmh: u32,
+    step_up: bool,
+    history: Mutex<BTreeMap<Uuid, LocationHistory>>,
+}
+
+impl fmt::Debug for GeoIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeoIp")
+            .field("location_db", &self.location_db)
+            .field("asn_db", &self.asn_db)
+            .field("max_travel_speed_kmh", &self.max_travel_speed_kmh)
+            .field("step_up", &self.step_up)
+            .finish()
+    }
+}
+
+impl GeoIp {
+    /// Locate addresses with a country or city database, and optionally an ASN database.
+    /// Sign ins that imply travel faster than `max_travel_speed_kmh` are unusual. If `step_up`
+    /// is set, unusual sign ins require multi-factor credentials.
+    pub fn new(
+        location_db: GeoIpDatabase,
+        asn_db: Option<GeoIpDatabase>,
+        max_travel_speed_kmh: u32,
+        step_up: bool,
+    ) -> Self {
+        GeoIp {
+            location_db,
+            asn_db,
+            max_travel_speed_kmh,
+            step_up,
+            history: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn locate_inner(&self, ip: IpAddr) -> Option<Located> {
+        let location = self
+            .location_db
+            .lookup(ip)
+            .inspect_err(|err| debug!(?err, %ip, "Unable to read location of address"))
+            .ok()
+            .flatten();
+        let network = self.asn_db.as_ref().and_then(|asn_db| {
+            asn_db
+                .lookup(ip)
+                .inspect_err(|err| debug!(?err, %ip, "Unable to read network of address"))
+                .ok()
+                .flatten()
+        });
+
+        if location.is_none() && network.is_none() {
+            return None;
+        }
+
+        let country = location.as_ref().and_then(|location| {
+            location
+                .get("country")
+                .or_else(|| location.get("registered_country"))
+                .and_then(|country| country.get("iso_code"))
+                .and_then(MmdbValue::as_str)
+                .map(str::to_string)
+        });
+        let coordinates = location.as_ref().and_then(|location| {
+            let coordinates = location.get("location")?;
+            let latitude = coordinates.get("latitude").and_then(MmdbValue::as_f64)?;
+            let longitude = coordinates.get("longitude").and_then(MmdbValue::as_f64)?;
+            Some((latitude, longitude))
+        });
+        let asn = network
+            .as_ref()
+            .and_then(|network| network.get("autonomous_system_number"))
+            .and_then(MmdbValue::as_u32);
+        let asn_org = network
+            .as_ref()
+            .and_then(|network| network.get("autonomous_system_organization"))
+            .and_then(MmdbValue::as_str)
+            .map(str::to_string);
+
+        Some(Located {
+            location: GeoLocation {
+                country,
+                asn,
+                asn_org,
+            },
+            coordinates,
+        })
+    }
+
+    /// Where this address is located, if it is found in the databases.
+    pub fn locate(&self, ip: IpAddr) -> Option<GeoLocation> {
+        self.locate_inner(ip).map(|located| located.location)
+    }
+
+    /// If unusual sign ins require multi-factor credentials.
+    pub(crate) fn step_up(&self) -> bool {
+        self.step_up
+    }
+
+    fn check(
+        &self,
+        history: Option<&LocationHistory>,
+        located: &Located,
+        ct: Duration,
+    ) -> Option<SessionAnomaly> {
+        // The first sign in of an account is where its history begins.
+        let history = history?;
+
+        if let (Some(last), Some(coordinates)) = (&history.last, located.coordinates) {
+            let distance = distance_km(last.coordinates, coordinates);
+            let elapsed = ct.saturating_sub(last.time);
+            let reachable = elapsed.as_secs_f64() / 3600.0 * f64::from(self.max_travel_speed_kmh);
+            if distance >= MIN_TRAVEL_DISTANCE_KM && distance > reachable {
+                return Some(SessionAnomaly::ImpossibleTravel {
+                    previous_country: last.country.clone(),
+                    country: located.location.country.clone(),
+                    distance_km: distance as u64,
+                    elapsed_secs: elapsed.as_secs(),
+                });
+            }
+        }
+
+        match &located.location.country {
+            Some(country)
+                if !history.countries.is_empty() && !history.countries.contains(country) =>
+            {
+                Some(SessionAnomaly::NewCountry {
+                    country: country.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Check if a sign in to this account from this source would be unusual, without
+    /// recording it.
+    pub(crate) fn assess(
+        &self,
+        uuid: Uuid,
+        source: &Source,
+        ct: Duration,
+    ) -> Option<SessionAnomaly> {
+        let (Source::Https(ip) | Source::Ldaps(ip)) = source else {
+            return None;
+        };
+        let located = self.locate_inner(*ip)?;
+        let history = self.history.lock().ok()?;
+        self.check(history.get(&uuid), &located, ct)
+    }
+
+    /// Record a sign in to this account from this source, returning where it is located and
+    /// if it was unusual.
+    pub(crate) fn record(
+        &self,
+        uuid: Uuid,
+        source: &Source,
+        ct: Duration,
+    ) -> (Option<GeoLocation>, Option<SessionAnomaly>) {
+        let (Source::Https(ip) | Source::Ldaps(ip)) = source else {
+            return (None, None);
+        };
+        let Some(located) = self.locate_inner(*ip) else {
+            return (None, None);
+        };
+        let Ok(mut history) = self.history.lock() else {
+            error!("GeoIP history lock poisoned");
+            return (Some(located.location), None);
+        };
+
+        let anomaly = self.check(history.get(&uuid), &located, ct);
+
+        let account_history = history.entry(uuid).or_default();
+        if let Some(country) = &located.location.country {
+            if account_history.countries.len() < MAX_KNOWN_COUNTRIES {
+                account_history.countries.insert(country.clone());
+            }
+        }
+        if let Some(coordinates) = located.coordinates {
+            account_history.last = Some(LastSignIn {
+                country: located.location.country.clone(),
+                coordinates,
+                time: ct,
+            });
+        }
+
+        (Some(located.location), anomaly)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GeoIp, GeoIpDatabase, GeoIpError, GeoLocation, SessionAnomaly};
+    use crate::prelude::*;
+    use crate::server::identity::Source;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    enum Child {
+        Empty,
+        Node(usize),
+        Data(usize),
+    }
+
+    fn encode_size(out: &mut Vec<u8>, data_type: u8, size: usize) {
+        if size < 29 {
+            out.push((data_type << 5) | size as u8);
+        } else {
+            out.push((data_type << 5) | 29);
+            out.push((size - 29) as u8);
+        }
+    }
+
+    fn encode_str(out: &mut Vec<u8>, value: &str) {
+        encode_size(out, 2, value.len());
+        out.extend_from_slice(value.as_bytes());
+    }
+
+    fn encode_double(out: &mut Vec<u8>, value: f64) {
+        encode_size(out, 3, 8);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn encode_u32(out: &mut Vec<u8>, value: u32) {
+        encode_size(out, 6, 4);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn encode_map(out: &mut Vec<u8>, len: usize) {
+        encode_size(out, 7, len);
+    }
+
+    /// The data of a city database for a country at these coordinates.
+    fn city(country: &str, latitude: f64, longitude: f64) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_map(&mut out, 2);
+        encode_str(&mut out, "country");
+        encode_map(&mut out, 1);
+        encode_str(&mut out, "iso_code");
+        encode_str(&mut out, country);
+        encode_str(&mut out, "location");
+        encode_map(&mut out, 2);
+        encode_str(&mut out, "latitude");
+        encode_double(&mut out, latitude);
+        encode_str(&mut out, "longitude");
+        encode_double(&mut out, longitude);
+        out
+    }
+
+    fn asn(number: u32, organisation: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_map(&mut out, 2);
+        encode_str(&mut out, "autonomous_system_number");
+        encode_u32(&mut out, number);
+        encode_str(&mut out, "autonomous_system_organization");
+        encode_str(&mut out, organisation);
+        out
+    }
+
+    /// Build an IPv4 database with a 24 bit record size that maps these networks to their
+    /// encoded data.
+    fn build_db(networks: &[(Ipv4Addr, u8, Vec<u8>)]) -> Vec<u8> {
+        let mut nodes = vec![[Child::Empty, Child::Empty]];
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+
+        for (index, (network, prefix, value)) in networks.iter().enumerate() {
+            offsets.push(data.len());
+            data.extend_from_slice(value);
+
+            let address = u32::from(*network);
+            let mut node = 0;
+            for bit in 0..*prefix {
+                let side = ((address >> (31 - bit)) & 1) as usize;
+                if bit + 1 == *prefix {
+                    nodes[node][side] = Child::Data(index);
+                } else if let Child::Node(next) = nodes[node][side] {
+                    node = next;
+                } else {
+                    nodes.push([Child::Empty, Child::Empty]);
+                    let next = nodes.len() - 1;
+                    nodes[node][side] = Child::Node(next);
+                    node = next;
+                }
+            }
+        }
+
+        let node_count = nodes.len();
+        let mut out = Vec::new();
+        for node in nodes.iter() {
+            for child in node.iter() {
+                let record = match child {
+                    Child::Empty => node_count,
+                    Child::Node(next) => *next,
+                    Child::Data(index) => node_count + 16 + offsets[*index],
+                };
+                out.extend_from_slice(&(record as u32).to_be_bytes()[1..]);
+            }
+        }
+        out.extend_from_slice(&[0; 16]);
+        out.extend_from_slice(&data);
+
+        out.extend_from_slice(b"\xab\xcd\xefMaxMind.com");
+        encode_map(&mut out, 4);
+        encode_str(&mut out, "node_count");
+        encode_u32(&mut out, node_count as u32);
+        encode_str(&mut out, "record_size");
+        out.extend_from_slice(&[(5 << 5) | 1, 24]);
+        encode_str(&mut out, "ip_version");
+        out.extend_from_slice(&[(5 << 5) | 1, 4]);
+        encode_str(&mut out, "database_type");
+        encode_str(&mut out, "Test-City");
+        out
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().expect("Invalid ip")
+    }
+
+    #[test]
+    fn test_geoip_database_lookup() {
+        // The second network points to the data of the first.
+        let pointer = vec![1 << 5, 0];
+        let db = build_db(&[
+            (Ipv4Addr::new(192, 0, 2, 0), 24, city("NZ", -41.3, 174.8)),
+            (Ipv4Addr::new(198, 51, 100, 0), 24, pointer),
+            (Ipv4Addr::new(203, 0, 113, 0), 24, city("AU", -33.9, 151.2)),
+        ]);
+        let db = GeoIpDatabase::from_bytes(db).expect("Invalid database");
+        assert_eq!(db.database_type(), "Test-City");
+
+        let asn_db = build_db(&[(Ipv4Addr::new(192, 0, 2, 0), 23, asn(64496, "Example Net"))]);
+        let asn_db = GeoIpDatabase::from_bytes(asn_db).expect("Invalid database");
+
+        let geoip = GeoIp::new(db, Some(asn_db), 1000, false);
+
+        assert_eq!(
+            geoip.locate(ip("192.0.2.10")),
+            Some(GeoLocation {
+                country: Some("NZ".to_string()),
+                asn: Some(64496),
+                asn_org: Some("Example Net".to_string()),
+            })
+        );
+        let located = geoip.locate_inner(ip("198.51.100.1")).expect("Not found");
+        assert_eq!(located.location.country.as_deref(), Some("NZ"));
+        assert_eq!(located.coordinates, Some((-41.3, 174.8)));
+        // IPv4 mapped addresses are looked up as IPv4.
+        assert_eq!(
+            geoip
+                .locate(ip("::ffff:203.0.113.5"))
+                .and_then(|location| location.country),
+            Some("AU".to_string())
+        );
+        assert_eq!(
+            geoip
+                .locate(ip("203.0.113.5"))
+                .map(|location| location.to_string()),
+            Some("AU".to_string())
+        );
+
+        assert_eq!(geoip.locate(ip("10.0.0.1")), None);
+        assert_eq!(geoip.locate(ip("2001:db8::1")), None);
+
+        assert_eq!(
+            GeoIpDatabase::from_bytes(b"not a database".to_vec()).unwrap_err(),
+            GeoIpError::InvalidMetadata
+        );
+    }
+
+    #[test]
+    fn test_geoip_session_anomaly() {
+        let db = build_db(&[
+            (Ipv4Addr::new(192, 0, 2, 0), 24, city("NZ", -41.3, 174.8)),
+            (Ipv4Addr::new(198, 51, 100, 0), 24, city("NZ", -36.8, 174.8)),
+            (Ipv4Addr::new(203, 0, 113, 0), 24, city("DE", 52.5, 13.4)),
+        ]);
+        let db = GeoIpDatabase::from_bytes(db).expect("Invalid database");
+        let geoip = GeoIp::new(db, None, 1000, true);
+
+        let wellington = Source::Https(ip("192.0.2.1"));
+        let auckland = Source::Https(ip("198.51.100.1"));
+        let berlin = Source::Https(ip("203.0.113.1"));
+        let ct = Duration::from_secs(86400);
+
+        // The first sign in is never unusual.
+        assert_eq!(geoip.assess(UUID_ADMIN, &berlin, ct), None);
+        assert_eq!(geoip.record(UUID_ADMIN, &wellington, ct).1, None);
+
+        // 500km within the country in an hour is possible.
+        let ct = ct + Duration::from_secs(3600);
+        assert_eq!(geoip.record(UUID_ADMIN, &auckland, ct).1, None);
+
+        // Across the world an hour later is not.
+        let ct = ct + Duration::from_secs(3600);
+        let anomaly = geoip.assess(UUID_ADMIN, &berlin, ct);
+        assert!(matches!(
+            anomaly,
+            Some(SessionAnomaly::ImpossibleTravel {
+                elapsed_secs: 3600,
+                ..
+            })
+        ));
+        // Assessing doesn't change the history.
+        assert_eq!(geoip.assess(UUID_ADMIN, &berlin, ct), anomaly);
+        assert_eq!(geoip.record(UUID_ADMIN, &berlin, ct).1, anomaly);
+
+        // A week later the travel is possible, but the country is only new once.
+        let ct = ct + Duration::from_secs(7 * 86400);
+        assert_eq!(geoip.record(UUID_ADMIN, &wellington, ct).1, None);
+        assert_eq!(
+            geoip.record(UUID_IDM_ADMIN, &wellington, ct),
+            (
+                Some(GeoLocation {
+                    country: Some("NZ".to_string()),
+                    asn: None,
+                    asn_org: None,
+                }),
+                None
+            )
+        );
+        let ct = ct + Duration::from_secs(7 * 86400);
+        assert_eq!(
+            geoip.record(UUID_IDM_ADMIN, &berlin, ct).1,
+            Some(SessionAnomaly::NewCountry {
+                country: "DE".to_string()
+            })
+        );
+
+        // Internal and unknown sources are not located.
+        assert_eq!(
+            geoip.record(UUID_ADMIN, &Source::Internal, ct),
+            (None, None)
+        );
+        assert_eq!(
+            geoip.record(UUID_ADMIN, &Source::Https(ip("10.0.0.1")), ct),
+            (None, None)
+        );
+    }
+}
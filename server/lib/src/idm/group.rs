@@ -1,7 +1,9 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
 
 use kanidm_proto::internal::{Group as ProtoGroup, UiHint};
 use kanidm_proto::v1::UnixGroupToken;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 use crate::entry::{Committed, Entry, EntryCommitted, EntrySealed, GetUuid};
@@ -33,6 +35,8 @@ where
     uuid: Uuid,
     // We'll probably add policy and claims later to this
     ui_hints: BTreeSet<UiHint>,
+    // When loaded from an account, the time that the account's membership lapses.
+    valid_until: Option<OffsetDateTime>,
 }
 
 macro_rules! try_from_entry {
@@ -53,6 +57,7 @@ macro_rules! try_from_entry {
             spn,
             uuid,
             ui_hints,
+            valid_until: None,
         })
     }};
 }
@@ -67,6 +72,11 @@ impl<T: GroupType> Group<T> {
     pub fn ui_hints(&self) -> &BTreeSet<UiHint> {
         &self.ui_hints
     }
+    /// If the membership this group was loaded through has lapsed at the current time.
+    pub(crate) fn membership_lapsed(&self, ct: Duration) -> bool {
+        self.valid_until
+            .is_some_and(|valid_until| valid_until <= OffsetDateTime::UNIX_EPOCH + ct)
+    }
     pub fn to_proto(&self) -> ProtoGroup {
         ProtoGroup {
             spn: self.spn.clone(),
@@ -261,11 +271,24 @@ where
             .collect()
     ));
 
-    let entries = qs.internal_search(f).map_err(|e| {
+    let mut entries = qs.internal_search(f).map_err(|e| {
         admin_error!(?e, "internal search failed");
         e
     })?;
 
+    // Some settings can only be taken from a single policy. The policies of groups that the
+    // account is directly a member of take precedence over nested groups, and are otherwise
+    // ordered by name so that the result is stable.
+    let direct_memberof = value.get_ava_refer(Attribute::DirectMemberOf);
+    entries.sort_by_cached_key(|entry| {
+        (
+            !direct_memberof.is_some_and(|direct| direct.contains(&entry.get_uuid())),
+            entry
+                .get_ava_single_iname(Attribute::Name)
+                .map(str::to_string),
+        )
+    });
+
     Ok(ResolvedAccountPolicy::fold_from(entries.iter().filter_map(
         |entry| {
             let acc_pol: Option<AccountPolicy> = entry.as_ref().into();
@@ -298,6 +321,8 @@ where
         e
     })?;
 
+    let membership_valid_until = resolve_membership_valid_until(value.get_uuid(), &entries);
+
     let mut groups = vec![];
     let mut unix_groups = Group::<Unix>::try_from_entry(value)
         .ok()
@@ -305,14 +330,124 @@ where
         .collect::<Vec<_>>();
 
     for entry in entries.iter() {
+        let valid_until = membership_valid_until
+            .get(&entry.get_uuid())
+            .copied()
+            .flatten();
+
         let entry = entry.as_ref();
         if entry.attribute_equality(Attribute::Class, &EntryClass::PosixGroup.into()) {
-            unix_groups.push(Group::<Unix>::try_from_entry::<EntrySealed>(entry)?);
+            let mut unix_group = Group::<Unix>::try_from_entry::<EntrySealed>(entry)?;
+            unix_group.valid_until = valid_until;
+            unix_groups.push(unix_group);
         }
 
         // No idea why we need to explicitly specify the type here
-        groups.push(Group::<()>::try_from_entry::<EntrySealed>(entry)?);
+        let mut group = Group::<()>::try_from_entry::<EntrySealed>(entry)?;
+        group.valid_until = valid_until;
+        groups.push(group);
     }
 
     Ok((groups, unix_groups))
 }
+
+/// Resolve the time that the membership of a member in each of its groups lapses, where
+/// `None` is a membership that never lapses. As groups can be nested, this is the latest
+/// time of any path of memberships to the group, and each path lapses as soon as any of
+/// the time-bound memberships along it do.
+fn resolve_membership_valid_until(
+    member_uuid: Uuid,
+    entries: &[Arc<EntrySealedCommitted>],
+) -> BTreeMap<Uuid, Option<OffsetDateTime>> {
+    fn earliest(a: Option<OffsetDateTime>, b: Option<OffsetDateTime>) -> Option<OffsetDateTime> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+
+    fn is_later(a: Option<OffsetDateTime>, b: Option<OffsetDateTime>) -> bool {
+        match (a, b) {
+            (None, Some(_)) => true,
+            (Some(a), Some(b)) => a > b,
+            (_, None) => false,
+        }
+    }
+
+    let groups: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            let members = entry
+                .get_ava_refer(Attribute::Member)
+                .cloned()
+                .unwrap_or_default();
+            let dyn_members = entry
+                .get_ava_refer(Attribute::DynMember)
+                .cloned()
+                .unwrap_or_default();
+            (
+                entry.get_uuid(),
+                members,
+                dyn_members,
+                entry.get_member_valid_until(),
+            )
+        })
+        .collect();
+
+    // Dynamic memberships are never time-bound.
+    let edge = |dyn_members: &BTreeSet<Uuid>,
+                member_valid_until: &BTreeMap<Uuid, OffsetDateTime>,
+                member: &Uuid| {
+        if dyn_members.contains(member) {
+            None
+        } else {
+            member_valid_until.get(member).copied()
+        }
+    };
+
+    let mut valid_until = BTreeMap::new();
+
+    for (group_uuid, members, dyn_members, member_valid_until) in groups.iter() {
+        if members.contains(&member_uuid) || dyn_members.contains(&member_uuid) {
+            valid_until.insert(
+                *group_uuid,
+                edge(dyn_members, member_valid_until, &member_uuid),
+            );
+        }
+    }
+
+    // Each pass extends the paths through nested groups by at least one group, so this
+    // is stable after at most as many passes as there are groups.
+    for _ in 0..groups.len() {
+        let mut changed = false;
+
+        for (group_uuid, members, dyn_members, member_valid_until) in groups.iter() {
+            for nested in members.iter().chain(dyn_members.iter()) {
+                let Some(nested_valid_until) = valid_until.get(nested).copied() else {
+                    continue;
+                };
+
+                let path = earliest(
+                    nested_valid_until,
+                    edge(dyn_members, member_valid_until, nested),
+                );
+
+                let is_better = valid_until
+                    .get(group_uuid)
+                    .map_or(true, |current| is_later(path, *current));
+
+                if is_better {
+                    valid_until.insert(*group_uuid, path);
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    valid_until
+}
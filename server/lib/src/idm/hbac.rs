@@ -0,0 +1,141 @@
+//! Host based access control rules define which accounts may access which hosts and pam
+//! services. The rules that apply to an account are resolved here and distributed to unix
+//! clients in the unix user token, so that the access decision is made from a single
+//! server side policy rather than from the configuration of each host.
+
+use crate::idm::account::Account;
+use crate::idm::accountpolicy::ResolvedAccountPolicy;
+use crate::idm::hostgroup::{resolve_host_group_names, resolve_host_tag_names};
+use crate::prelude::*;
+use kanidm_proto::v1::{UnixHbacPolicy, UnixHbacRule};
+
+/// Resolve the host based access control rules that apply to an account, either directly or
+/// through membership of a group. If no rules are defined in the domain then `None` is
+/// returned so that clients continue to use their local access configuration.
+pub(crate) fn load_unix_hbac_policy<'a, TXN>(
+    account: &Account,
+    qs: &mut TXN,
+) -> Result<Option<UnixHbacPolicy>, OperationError>
+where
+    TXN: QueryServerTransaction<'a>,
+{
+    let f_rules = filter!(f_eq(Attribute::Class, EntryClass::HbacRule.into()));
+
+    if !qs.internal_exists(f_rules)? {
+        return Ok(None);
+    }
+
+    let member_filter = std::iter::once(account.uuid)
+        .chain(account.groups.iter().map(|g| *g.uuid()))
+        .map(|uuid| f_eq(Attribute::HbacMember, PartialValue::Refer(uuid)))
+        .collect();
+
+    let rules = qs.internal_search(filter!(f_and!([
+        f_eq(Attribute::Class, EntryClass::HbacRule.into()),
+        f_or(member_filter)
+    ])))?;
+
+    let mut policy = UnixHbacPolicy::default();
+
+    for rule in rules {
+        let name = rule
+            .get_ava_single_iname(Attribute::Name)
+            .map(str::to_string)
+            .unwrap_or_else(|| rule.get_uuid().to_string());
+
+        let mut hosts: Vec<String> = rule
+            .get_ava_iter_iutf8(Attribute::HbacHost)
+            .map(|i| i.map(str::to_string).collect())
+            .unwrap_or_default();
+
+        if let Some(host_groups) = rule.get_ava_refer(Attribute::HbacHostGroup) {
+            let group_hosts = resolve_host_group_names(host_groups, qs)?;
+            if hosts.is_empty() && group_hosts.is_empty() {
+                // The rule is limited to host groups that currently contain no machines. As
+                // a rule without hosts applies to all hosts, the rule must be skipped so that
+                // it matches nothing.
+                trace!(%name, "hbac rule host groups are empty, skipping");
+                continue;
+            }
+            for host in group_hosts {
+                if !hosts.contains(&host) {
+                    hosts.push(host);
+                }
+            }
+        }
+
+        let hbac_rule = UnixHbacRule {
+            name,
+            hosts,
+            services: rule
+                .get_ava_iter_iutf8(Attribute::HbacService)
+                .map(|i| i.map(str::to_string).collect())
+                .unwrap_or_default(),
+        };
+
+        if rule
+            .get_ava_single_bool(Attribute::HbacDeny)
+            .unwrap_or(false)
+        {
+            policy.deny.push(hbac_rule);
+        } else {
+            policy.allow.push(hbac_rule);
+        }
+    }
+
+    trace!(?policy, "resolved hbac policy");
+
+    Ok(Some(policy))
+}
+
+/// Resolve the names of the hosts that an account may log in to from the login host tags of
+/// its account policy. If the policy doesn't restrict logins to tagged hosts then `None` is
+/// returned.
+pub(crate) fn load_unix_login_hosts<'a, TXN>(
+    account_policy: &ResolvedAccountPolicy,
+    qs: &mut TXN,
+) -> Result<Option<Vec<String>>, OperationError>
+where
+    TXN: QueryServerTransaction<'a>,
+{
+    let Some(tags) = account_policy.login_host_tags() else {
+        return Ok(None);
+    };
+
+    let hosts = resolve_host_tag_names(tags, qs)?;
+
+    trace!(?hosts, "resolved login hosts");
+
+    Ok(Some(hosts.into_iter().collect()))
+}
+
+/// Determine if a host is permitted to authenticate an account by the login host tags of its
+/// account policy. Only an enrolled host can prove which tags it carries, so when logins are
+/// restricted any other client is denied.
+pub(crate) fn check_login_host<'a, TXN>(
+    account_policy: &ResolvedAccountPolicy,
+    ident: &Identity,
+    qs: &mut TXN,
+) -> Result<bool, OperationError>
+where
+    TXN: QueryServerTransaction<'a>,
+{
+    let Some(tags) = account_policy.login_host_tags() else {
+        return Ok(true);
+    };
+
+    let Some(host_uuid) = ident.get_uuid() else {
+        return Ok(false);
+    };
+
+    let host_entry = qs.internal_search_uuid(host_uuid)?;
+
+    if !host_entry.attribute_equality(Attribute::Class, &EntryClass::Host.into()) {
+        return Ok(false);
+    }
+
+    Ok(host_entry
+        .get_ava_iter_iutf8(Attribute::HostTag)
+        .map(|mut host_tags| host_tags.any(|tag| tags.contains(tag)))
+        .unwrap_or(false))
+}
@@ -0,0 +1,174 @@
+//! Hooks are external commands, configured by the server administrator, that are run after
+//! entries matching their filters are changed. They allow site specific automation such as
+//! creating home directories without changes to the server. Hooks use the same filters and
+//! events as webhooks, but as they are trusted by the administrator, the event may also carry
+//! the values of selected attributes of the entry.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::idm::audit::AuditEvent;
+use crate::idm::server::IdmServerProxyReadTransaction;
+use crate::idm::webhook::{EntryChange, WebhookFilter, WebhookPayload};
+use crate::prelude::*;
+
+/// The event sent to a hook.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HookPayload {
+    #[serde(flatten)]
+    pub event: WebhookPayload,
+    /// The values of the attributes requested by the hook. Attributes that aren't present
+    /// on the entry are omitted. Credentials are only described, never included.
+    pub entry: BTreeMap<Attribute, Vec<String>>,
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    /// Determine the events that must be sent to a hook with these filters as a result of
+    /// this activity.
+    pub fn hook_payloads(
+        &mut self,
+        event: &AuditEvent,
+        filters: &[WebhookFilter],
+        entry_attrs: &BTreeSet<Attribute>,
+    ) -> Result<Vec<HookPayload>, OperationError> {
+        let empty_attrs = BTreeSet::new();
+
+        let Some(EntryChange {
+            action,
+            actor,
+            targets,
+            attrs,
+            time,
+        }) = EntryChange::from_event(event, &empty_attrs)
+        else {
+            return Ok(Vec::with_capacity(0));
+        };
+
+        let mut payloads = Vec::new();
+
+        for target in targets.iter().copied() {
+            // Deleted entries are in the recycle bin, and still have their classes.
+            let entry = match self.qs_read.internal_search_all_uuid(target) {
+                Ok(entry) => entry,
+                Err(OperationError::NoMatchingEntries) => {
+                    debug!(?target, "Entry no longer exists, unable to send to hooks");
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            let classes: BTreeSet<String> = entry
+                .get_ava_iter_iutf8(Attribute::Class)
+                .into_iter()
+                .flatten()
+                .map(str::to_string)
+                .collect();
+
+            if !filters
+                .iter()
+                .any(|filter| filter.matches(action, &classes, attrs))
+            {
+                continue;
+            }
+
+            let entry_values = entry_attrs
+                .iter()
+                .filter_map(|attr| {
+                    entry
+                        .get_ava_set(attr)
+                        .map(|vs| (attr.clone(), vs.to_proto_string_clone_iter().collect()))
+                })
+                .collect();
+
+            payloads.push(HookPayload {
+                event: WebhookPayload {
+                    id: Uuid::new_v4(),
+                    action,
+                    target,
+                    classes,
+                    attrs: attrs.clone(),
+                    actor,
+                    time,
+                },
+                entry: entry_values,
+            });
+        }
+
+        Ok(payloads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
+
+    use kanidm_proto::internal::WebhookAction;
+    use time::OffsetDateTime;
+
+    use crate::idm::audit::{AuditEvent, AuditSource};
+    use crate::idm::webhook::WebhookFilter;
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[idm_test]
+    async fn test_idm_hook_payloads(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let time = OffsetDateTime::UNIX_EPOCH + ct;
+
+        let person_uuid = Uuid::new_v4();
+        let group_uuid = Uuid::new_v4();
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let person = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("test_person")),
+            (Attribute::Uuid, Value::Uuid(person_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("Test Person"))
+        );
+
+        let group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("test_group")),
+            (Attribute::Uuid, Value::Uuid(group_uuid))
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![person, group])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let created = AuditEvent::EntriesCreated {
+            source: AuditSource::Internal,
+            actor: UUID_ADMIN,
+            targets: BTreeSet::from([person_uuid, group_uuid]),
+            time,
+        };
+
+        let filters = vec![WebhookFilter::from_str("created:person").expect("Invalid filter")];
+        let entry_attrs = BTreeSet::from([Attribute::Name, Attribute::Mail]);
+
+        let payloads = idms_prox_read
+            .hook_payloads(&created, &filters, &entry_attrs)
+            .expect("Failed to determine hook payloads");
+
+        // Only the person matches, and attributes that aren't present are omitted.
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].event.target, person_uuid);
+        assert_eq!(payloads[0].event.action, WebhookAction::Created);
+        assert_eq!(
+            payloads[0].entry.get(&Attribute::Name),
+            Some(&vec!["test_person".to_string()])
+        );
+        assert!(!payloads[0].entry.contains_key(&Attribute::Mail));
+    }
+}
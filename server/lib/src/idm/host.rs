@@ -0,0 +1,227 @@
+//! Hosts are machine accounts that represent the unix clients of the domain. An administrator
+//! creates the host and issues it a one time join token, which the machine exchanges for its
+//! own credential when it enrolls. From then on the machine authenticates as the host, and
+//! rotates its credential before it expires. As hosts are service accounts, they can be
+//! targeted by host groups and host based access control rules by their name.
+
+use std::time::Duration;
+
+use compact_jwt::JwsCompact;
+use openssl::memcmp;
+use time::OffsetDateTime;
+
+use crate::idm::server::IdmServerProxyWriteTransaction;
+use crate::idm::serviceaccount::GenerateApiTokenEvent;
+use crate::prelude::*;
+use crate::utils::password_from_random;
+
+pub struct GenerateHostJoinTokenEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Which host is the token for?
+    pub target: Uuid,
+    // How long is the token valid for? Defaults to HOST_JOIN_TOKEN_DEFAULT_TTL.
+    pub ttl: Option<Duration>,
+}
+
+pub struct HostEnrollEvent {
+    // The name of the host to enroll as.
+    pub name: String,
+    // The join token that was issued for the host.
+    pub join_token: String,
+}
+
+pub struct HostRotateCredentialEvent {
+    // The enrolled host, authenticated with its current credential.
+    pub ident: Identity,
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    /// Issue a one time join token for a host, replacing any token that was previously issued.
+    /// The token allows a machine to enroll as the host until it expires.
+    pub fn host_generate_join_token(
+        &mut self,
+        ev: &GenerateHostJoinTokenEvent,
+        ct: Duration,
+    ) -> Result<String, OperationError> {
+        let ttl = ev
+            .ttl
+            .unwrap_or(Duration::from_secs(HOST_JOIN_TOKEN_DEFAULT_TTL));
+
+        if ttl > Duration::from_secs(HOST_JOIN_TOKEN_MAX_TTL) {
+            error!(?ttl, "Host join token ttl exceeds the maximum allowed");
+            return Err(OperationError::InvalidRequestState);
+        }
+
+        let join_token = password_from_random();
+        let expiry = OffsetDateTime::UNIX_EPOCH + ct + ttl;
+
+        let modlist = ModifyList::new_list(vec![
+            m_purge(Attribute::HostJoinToken),
+            Modify::Present(Attribute::HostJoinToken, Value::new_secret_str(&join_token)),
+            m_purge(Attribute::HostJoinTokenExpiry),
+            Modify::Present(Attribute::HostJoinTokenExpiry, Value::new_datetime(expiry)),
+        ]);
+
+        // Join tokens can only be issued to hosts, not to other kinds of account.
+        self.qs_write
+            .impersonate_modify(
+                // Filter as executed
+                &filter!(f_and!([
+                    f_eq(Attribute::Uuid, PartialValue::Uuid(ev.target)),
+                    f_eq(Attribute::Class, EntryClass::Host.into())
+                ])),
+                // Filter as intended (acp)
+                &filter_all!(f_and!([
+                    f_eq(Attribute::Uuid, PartialValue::Uuid(ev.target)),
+                    f_eq(Attribute::Class, EntryClass::Host.into())
+                ])),
+                &modlist,
+                // Provide the event to impersonate
+                &ev.ident,
+            )
+            .map_err(|err| {
+                error!(?err, "Failed to generate host join token");
+                err
+            })?;
+
+        Ok(join_token)
+    }
+
+    /// Exchange a join token for the credential of the host. The join token can only be used
+    /// once, and any credential previously issued to the host is revoked.
+    #[instrument(level = "debug", skip_all)]
+    pub fn host_enroll(
+        &mut self,
+        ev: &HostEnrollEvent,
+        ct: Duration,
+    ) -> Result<JwsCompact, OperationError> {
+        self.qs_write.refuse_in_maintenance_mode()?;
+
+        let mut hosts = self.qs_write.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Host.into()),
+            f_eq(Attribute::Name, PartialValue::new_iname(&ev.name))
+        ])))?;
+
+        let Some(host_entry) = hosts.pop() else {
+            security_info!(name = %ev.name, "Host does not exist, denying enrollment");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let token_valid = host_entry
+            .get_ava_single_secret(Attribute::HostJoinToken)
+            .map(|join_token| {
+                join_token.len() == ev.join_token.len()
+                    && memcmp::eq(join_token.as_bytes(), ev.join_token.as_bytes())
+            })
+            .unwrap_or(false);
+
+        if !token_valid {
+            security_info!(name = %ev.name, "Host join token is invalid, denying enrollment");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let not_expired = host_entry
+            .get_ava_single_datetime(Attribute::HostJoinTokenExpiry)
+            .map(|expiry| OffsetDateTime::UNIX_EPOCH + ct < expiry)
+            .unwrap_or(false);
+
+        if !not_expired {
+            security_info!(name = %ev.name, "Host join token has expired, denying enrollment");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let host_uuid = host_entry.get_uuid();
+
+        // The join token is consumed, and the host may only have a single credential, so
+        // enrolling again revokes the credential of any machine that previously enrolled.
+        let mut modlist = ModifyList::new_list(vec![
+            m_purge(Attribute::HostJoinToken),
+            m_purge(Attribute::HostJoinTokenExpiry),
+        ]);
+
+        if let Some(api_tokens) = host_entry.get_ava_as_apitoken_map(Attribute::ApiTokenSession) {
+            for (token_id, _) in api_tokens
+                .iter()
+                .filter(|(_, token)| token.label == HOST_CREDENTIAL_LABEL)
+            {
+                modlist.push_mod(Modify::Removed(
+                    Attribute::ApiTokenSession,
+                    PartialValue::Refer(*token_id),
+                ));
+            }
+        }
+
+        self.qs_write.internal_modify_uuid(host_uuid, &modlist)?;
+
+        security_info!(name = %ev.name, "Host enrolled");
+
+        self.host_issue_credential(host_uuid, ct)
+    }
+
+    /// Issue a new credential to an enrolled host, revoking the credential that it
+    /// authenticated with.
+    #[instrument(level = "debug", skip_all)]
+    pub fn host_rotate_credential(
+        &mut self,
+        ev: &HostRotateCredentialEvent,
+        ct: Duration,
+    ) -> Result<JwsCompact, OperationError> {
+        let Some(host_uuid) = ev.ident.get_uuid() else {
+            error!("Only hosts may rotate a host credential");
+            return Err(OperationError::AccessDenied);
+        };
+
+        self.qs_write.check_maintenance_mode(&ev.ident)?;
+
+        let host_entry = self.qs_write.internal_search_uuid(host_uuid)?;
+
+        if !host_entry.attribute_equality(Attribute::Class, &EntryClass::Host.into()) {
+            error!("Only hosts may rotate a host credential");
+            return Err(OperationError::AccessDenied);
+        }
+
+        // The host must have authenticated with its credential, not an api token that was
+        // issued to it for another purpose.
+        let session_id = ev.ident.get_session_id();
+        let is_host_credential = host_entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .and_then(|api_tokens| api_tokens.get(&session_id))
+            .map(|token| token.label == HOST_CREDENTIAL_LABEL)
+            .unwrap_or(false);
+
+        if !is_host_credential {
+            error!("Host did not authenticate with a host credential");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let credential = self.host_issue_credential(host_uuid, ct)?;
+
+        self.qs_write.internal_modify_uuid(
+            host_uuid,
+            &ModifyList::new_remove(Attribute::ApiTokenSession, PartialValue::Refer(session_id)),
+        )?;
+
+        Ok(credential)
+    }
+
+    fn host_issue_credential(
+        &mut self,
+        target: Uuid,
+        ct: Duration,
+    ) -> Result<JwsCompact, OperationError> {
+        // Hosts only need to read accounts and groups to resolve them for unix clients.
+        let gte = GenerateApiTokenEvent {
+            ident: Identity::from_internal(),
+            target,
+            label: HOST_CREDENTIAL_LABEL.to_string(),
+            expiry: Some(
+                OffsetDateTime::UNIX_EPOCH + ct + Duration::from_secs(HOST_CREDENTIAL_EXPIRY),
+            ),
+            read_write: false,
+            permissions: Vec::with_capacity(0),
+        };
+
+        self.service_account_generate_api_token(&gte, ct)
+    }
+}
@@ -0,0 +1,100 @@
+//! Host groups are named sets of machine accounts that policy, such as host based access
+//! control rules, can target as a whole. Machines are members of a host group either when
+//! they are named directly, or when they carry any of the host tags of the group, so that a
+//! fleet of machines can be targeted without enumerating each of them.
+
+use crate::prelude::*;
+use std::collections::BTreeSet;
+
+/// Resolve the host names of the machine accounts that are members of the given host
+/// groups. Machine account names are used as host names, so these should match the names
+/// that the hosts identify themselves by.
+pub(crate) fn resolve_host_group_names<'a, TXN>(
+    host_groups: &BTreeSet<Uuid>,
+    qs: &mut TXN,
+) -> Result<BTreeSet<String>, OperationError>
+where
+    TXN: QueryServerTransaction<'a>,
+{
+    if host_groups.is_empty() {
+        return Ok(BTreeSet::default());
+    }
+
+    let f_groups = host_groups
+        .iter()
+        .map(|uuid| f_eq(Attribute::Uuid, PartialValue::Uuid(*uuid)))
+        .collect();
+
+    let groups = qs.internal_search(filter!(f_and!([
+        f_eq(Attribute::Class, EntryClass::HostGroup.into()),
+        f_or(f_groups)
+    ])))?;
+
+    let mut f_hosts = Vec::with_capacity(0);
+
+    for group in groups.iter() {
+        if let Some(members) = group.get_ava_refer(Attribute::HostGroupMember) {
+            f_hosts.extend(
+                members
+                    .iter()
+                    .map(|uuid| f_eq(Attribute::Uuid, PartialValue::Uuid(*uuid))),
+            );
+        }
+
+        if let Some(tags) = group.get_ava_iter_iutf8(Attribute::HostTag) {
+            f_hosts.extend(tags.map(|tag| f_eq(Attribute::HostTag, PartialValue::new_iutf8(tag))));
+        }
+    }
+
+    if f_hosts.is_empty() {
+        return Ok(BTreeSet::default());
+    }
+
+    let hosts = qs.internal_search(filter!(f_and!([
+        f_eq(Attribute::Class, EntryClass::ServiceAccount.into()),
+        f_or(f_hosts)
+    ])))?;
+
+    let names = hosts
+        .iter()
+        .filter_map(|host| host.get_ava_single_iname(Attribute::Name))
+        .map(str::to_string)
+        .collect();
+
+    trace!(?names, "resolved host group members");
+
+    Ok(names)
+}
+
+/// Resolve the host names of the machine accounts that carry any of the given host tags.
+pub(crate) fn resolve_host_tag_names<'a, TXN>(
+    tags: &BTreeSet<String>,
+    qs: &mut TXN,
+) -> Result<BTreeSet<String>, OperationError>
+where
+    TXN: QueryServerTransaction<'a>,
+{
+    if tags.is_empty() {
+        return Ok(BTreeSet::default());
+    }
+
+    let f_tags = tags
+        .iter()
+        .map(|tag| f_eq(Attribute::HostTag, PartialValue::new_iutf8(tag)))
+        .collect();
+
+    let hosts = qs.internal_search(filter!(f_and!([
+        f_eq(Attribute::Class, EntryClass::ServiceAccount.into()),
+        f_or(f_tags)
+    ])))?;
+
+    let names = hosts
+        .iter()
+        .filter_map(|host| host.get_ava_single_iname(Attribute::Name))
+        .map(str::to_string)
+        .collect();
+
+    trace!(?names, "resolved host tag members");
+
+    Ok(names)
+}
@@ -0,0 +1,339 @@
+//! Members of idm_impersonation_admins may be issued a short lived session that acts as another
+//! person, so that they can investigate an issue that person has reported with an application.
+//! These sessions are read only, are marked in the claims of the token with the impersonator,
+//! and are always audited. High privilege accounts can't be impersonated, and an impersonation
+//! session can never be used to impersonate again.
+
+use std::time::Duration;
+
+use compact_jwt::{Jws, JwsCompact};
+use time::OffsetDateTime;
+
+use crate::idm::account::Account;
+use crate::idm::audit::AuditEvent;
+use crate::idm::server::IdmServerProxyWriteTransaction;
+use crate::prelude::*;
+use crate::value::{AuthType, Session, SessionState};
+
+/// The length of an impersonation session if none is requested, in seconds.
+pub const IMPERSONATION_SESSION_DEFAULT_EXPIRY: u64 = 900;
+/// The longest that an impersonation session may last, in seconds.
+pub const IMPERSONATION_SESSION_MAX_EXPIRY: u64 = 3600;
+
+pub struct ImpersonateEvent {
+    // Who initiated this? They must be a member of idm_impersonation_admins.
+    pub ident: Identity,
+    // The person to impersonate.
+    pub target: Uuid,
+    // How long the session should last.
+    pub expiry: Option<Duration>,
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub fn impersonate_session(
+        &mut self,
+        ie: &ImpersonateEvent,
+        ct: Duration,
+    ) -> Result<JwsCompact, OperationError> {
+        let Some(actor) = ie.ident.get_uuid() else {
+            error!("Only persons may impersonate another person");
+            return Err(OperationError::AccessDenied);
+        };
+
+        if !ie.ident.is_memberof(UUID_IDM_IMPERSONATION_ADMINS) {
+            security_info!(%actor, "Impersonation denied - not a member of idm_impersonation_admins");
+            return Err(OperationError::AccessDenied);
+        }
+
+        // Impersonation sessions are read only, so this also prevents an impersonation
+        // session being used to impersonate again.
+        if ie.ident.access_scope() != AccessScope::ReadWrite {
+            error!("Impersonation requires a read-write session");
+            return Err(OperationError::AccessDenied);
+        }
+
+        if ie.target == actor {
+            error!("A person may not impersonate themself");
+            return Err(OperationError::InvalidRequestState);
+        }
+
+        let expiry = ie
+            .expiry
+            .unwrap_or(Duration::from_secs(IMPERSONATION_SESSION_DEFAULT_EXPIRY));
+        if expiry.is_zero() || expiry > Duration::from_secs(IMPERSONATION_SESSION_MAX_EXPIRY) {
+            error!(
+                ?expiry,
+                "Impersonation session expiry must be between 1 and {} seconds",
+                IMPERSONATION_SESSION_MAX_EXPIRY
+            );
+            return Err(OperationError::InvalidRequestState);
+        }
+
+        let entry = self.qs_write.internal_search_uuid(ie.target)?;
+
+        if !entry.attribute_equality(Attribute::Class, &EntryClass::Person.into()) {
+            error!("Only persons may be impersonated");
+            return Err(OperationError::AccessDenied);
+        }
+
+        // Otherwise impersonation would be a path to gain the privileges of another admin.
+        if entry.attribute_equality(
+            Attribute::MemberOf,
+            &PartialValue::Refer(UUID_IDM_HIGH_PRIVILEGE),
+        ) {
+            security_info!(%actor, target = %ie.target, "Impersonation denied - target is high privilege");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let (account, account_policy) =
+            Account::try_from_entry_with_policy(entry.as_ref(), &mut self.qs_write)?;
+
+        if !account.is_within_valid_time(ct) {
+            error!(target = %ie.target, "Impersonation denied - account is not valid");
+            return Err(OperationError::SessionExpired);
+        }
+
+        let session_id = Uuid::new_v4();
+        let mut uat = account
+            .to_userauthtoken(session_id, SessionScope::ReadOnly, ct, &account_policy)
+            .ok_or(OperationError::AU0004UserAuthTokenInvalid)?;
+
+        let expiry = uat.issued_at + expiry;
+        uat.expiry = Some(expiry);
+        uat.impersonated_by = Some(actor);
+
+        let session = Value::Session(
+            session_id,
+            Session {
+                label: "Impersonation Session".to_string(),
+                state: SessionState::ExpiresAt(expiry),
+                issued_at: uat.issued_at,
+                issued_by: IdentityId::User(actor),
+                // No credential of the account issued this session.
+                cred_id: actor,
+                scope: SessionScope::ReadOnly,
+                type_: AuthType::Impersonation,
+            },
+        );
+
+        // The session is recorded on the person, so that they can see it and revoke it.
+        self.qs_write
+            .internal_modify_uuid(
+                ie.target,
+                &ModifyList::new_list(vec![Modify::Present(
+                    Attribute::UserAuthTokenSession,
+                    session,
+                )]),
+            )
+            .map_err(|err| {
+                error!(?err, "Failed to record impersonation session");
+                err
+            })?;
+
+        let jws = Jws::into_json(&uat).map_err(|err| {
+            error!(?err, "Failed to serialise impersonation session");
+            OperationError::AU0002JwsSerialisation
+        })?;
+
+        let token = self
+            .qs_write
+            .get_domain_key_object_handle()?
+            .jws_es256_sign(&jws, ct)
+            .map_err(|err| {
+                error!(?err, "Failed to sign impersonation session");
+                OperationError::AU0003JwsSignature
+            })?;
+
+        security_info!(%actor, target = %ie.target, %session_id, "Issued impersonation session");
+
+        self.submit_audit_event(AuditEvent::ImpersonationSessionIssued {
+            source: ie.ident.source().clone().into(),
+            actor,
+            uuid: account.uuid,
+            spn: account.spn,
+            session_id,
+            expiry,
+            time: OffsetDateTime::UNIX_EPOCH + ct,
+        });
+
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ImpersonateEvent, IMPERSONATION_SESSION_MAX_EXPIRY};
+    use kanidm_proto::internal::UatPurpose;
+
+    use crate::idm::server::IdmServerTransaction;
+    use crate::idm::ClientAuthInfo;
+    use crate::prelude::*;
+    use crate::value::{AuthType, SessionState};
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[idm_test]
+    async fn test_idm_impersonate_session(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let actor_uuid = Uuid::new_v4();
+        let target_uuid = Uuid::new_v4();
+
+        let e_actor = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Uuid, Value::Uuid(actor_uuid)),
+            (Attribute::Name, Value::new_iname("helpdesk")),
+            (Attribute::DisplayName, Value::new_utf8s("Help Desk"))
+        );
+
+        let e_target = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Uuid, Value::Uuid(target_uuid)),
+            (Attribute::Name, Value::new_iname("kevin")),
+            (Attribute::DisplayName, Value::new_utf8s("Kevin"))
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![e_actor, e_target])
+            .is_ok());
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(actor_uuid)
+            .expect("Unable to find person");
+        let ie = ImpersonateEvent {
+            ident: Identity::from_impersonate_entry_readwrite(entry),
+            target: target_uuid,
+            expiry: None,
+        };
+
+        // Denied without membership of idm_impersonation_admins.
+        assert!(matches!(
+            idms_prox_write.impersonate_session(&ie, ct),
+            Err(OperationError::AccessDenied)
+        ));
+
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_IMPERSONATION_ADMINS,
+                &ModifyList::new_append(Attribute::Member, Value::Refer(actor_uuid)),
+            )
+            .expect("Unable to add member");
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(actor_uuid)
+            .expect("Unable to find person");
+        let ident = Identity::from_impersonate_entry_readwrite(entry);
+
+        let ie = ImpersonateEvent {
+            ident: ident.clone(),
+            target: target_uuid,
+            expiry: None,
+        };
+
+        let token = idms_prox_write
+            .impersonate_session(&ie, ct)
+            .expect("Unable to impersonate");
+
+        // The token is marked with the impersonator and is read only.
+        let uat = idms_prox_write
+            .validate_client_auth_info_to_uat(ClientAuthInfo::from(token), ct)
+            .expect("Unable to validate impersonation session");
+        assert_eq!(uat.uuid, target_uuid);
+        assert_eq!(uat.impersonated_by, Some(actor_uuid));
+        assert_eq!(uat.purpose, UatPurpose::ReadOnly);
+
+        // The session is recorded on the person.
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(target_uuid)
+            .expect("Unable to find person");
+        let sessions = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .expect("No sessions present");
+        assert_eq!(sessions.len(), 1);
+        let session = sessions.values().next().expect("No sessions present");
+        assert_eq!(session.type_, AuthType::Impersonation);
+        assert_eq!(session.issued_by, IdentityId::User(actor_uuid));
+        assert!(matches!(session.state, SessionState::ExpiresAt(_)));
+
+        // A read only session can't impersonate.
+        let ie_ro = ImpersonateEvent {
+            ident: ident.project_with_scope(AccessScope::ReadOnly),
+            target: target_uuid,
+            expiry: None,
+        };
+        assert!(matches!(
+            idms_prox_write.impersonate_session(&ie_ro, ct),
+            Err(OperationError::AccessDenied)
+        ));
+
+        // The expiry is limited.
+        let ie_long = ImpersonateEvent {
+            ident: ident.clone(),
+            target: target_uuid,
+            expiry: Some(Duration::from_secs(IMPERSONATION_SESSION_MAX_EXPIRY + 1)),
+        };
+        assert!(matches!(
+            idms_prox_write.impersonate_session(&ie_long, ct),
+            Err(OperationError::InvalidRequestState)
+        ));
+
+        // Can't impersonate yourself.
+        let ie_self = ImpersonateEvent {
+            ident: ident.clone(),
+            target: actor_uuid,
+            expiry: None,
+        };
+        assert!(matches!(
+            idms_prox_write.impersonate_session(&ie_self, ct),
+            Err(OperationError::InvalidRequestState)
+        ));
+
+        // High privilege accounts can't be impersonated.
+        let admin_uuid = Uuid::new_v4();
+        let e_admin = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Uuid, Value::Uuid(admin_uuid)),
+            (Attribute::Name, Value::new_iname("admin_person")),
+            (Attribute::DisplayName, Value::new_utf8s("Admin Person"))
+        );
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![e_admin])
+            .is_ok());
+
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_ADMINS,
+                &ModifyList::new_append(Attribute::Member, Value::Refer(admin_uuid)),
+            )
+            .expect("Unable to add member");
+
+        let ie_admin = ImpersonateEvent {
+            ident,
+            target: admin_uuid,
+            expiry: None,
+        };
+        assert!(matches!(
+            idms_prox_write.impersonate_session(&ie_admin, ct),
+            Err(OperationError::AccessDenied)
+        ));
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+}
@@ -0,0 +1,161 @@
+//! Inspect the internal representation of an entry. This exposes the change metadata of the
+//! entry, such as when each attribute was last changed and by which server, so that support
+//! cases involving replication or synchronisation can be diagnosed without reading the
+//! database directly.
+
+use std::collections::BTreeMap;
+
+use kanidm_proto::internal::{EntryInspectAttr, EntryInspectResponse, EntryInspectState};
+
+use crate::idm::server::IdmServerProxyReadTransaction;
+use crate::prelude::*;
+use crate::repl::entry::State;
+
+impl IdmServerProxyReadTransaction<'_> {
+    pub fn inspect_entry(
+        &mut self,
+        ident: &Identity,
+        target: Uuid,
+    ) -> Result<EntryInspectResponse, OperationError> {
+        // This bypasses access controls so that recycled entries and tombstones can be
+        // inspected. Limit this to system administrators.
+        if !ident.is_memberof(UUID_SYSTEM_ADMINS) {
+            security_access!("Identity is not permitted to inspect entries");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let entry = self
+            .qs_read
+            .internal_search(filter_all!(f_eq(
+                Attribute::Uuid,
+                PartialValue::Uuid(target)
+            )))?
+            .pop()
+            .ok_or(OperationError::NoMatchingEntries)?;
+
+        let (state, state_at, changes) = match entry.get_changestate().current() {
+            State::Live { at, changes } => {
+                let state = if entry.mask_recycled().is_some() {
+                    EntryInspectState::Live
+                } else {
+                    EntryInspectState::Recycled
+                };
+                (state, at, Some(changes))
+            }
+            State::Tombstone { at } => (EntryInspectState::Tombstone, at, None),
+        };
+
+        let attrs = entry
+            .get_ava_iter()
+            .map(|(attr, vs)| {
+                let changed_at = changes
+                    .and_then(|changes| changes.get(attr))
+                    .map(|cid| cid.to_string());
+
+                (
+                    attr.to_string(),
+                    EntryInspectAttr {
+                        values: vs.to_proto_string_clone_iter().collect(),
+                        changed_at,
+                    },
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        Ok(EntryInspectResponse {
+            uuid: entry.get_uuid(),
+            state,
+            state_at: state_at.to_string(),
+            sync_parent_uuid: entry.get_ava_single_refer(Attribute::SyncParentUuid),
+            attrs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use kanidm_proto::internal::EntryInspectState;
+
+    #[idm_test]
+    async fn test_idm_inspect_entry(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = duration_from_epoch_now();
+        let grp_uuid = Uuid::new_v4();
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let e_grp = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Uuid, Value::Uuid(grp_uuid)),
+            (Attribute::Name, Value::new_iname("test_inspect_group"))
+        );
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![e_grp])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let ct = ct + Duration::from_secs(1);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                grp_uuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::Description,
+                    Value::new_utf8s("inspected")
+                ),
+            )
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let admin = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_ADMIN)
+            .map(Identity::from_impersonate_entry_readonly)
+            .expect("Unable to get admin");
+
+        let inspect = idms_prox_read
+            .inspect_entry(&admin, grp_uuid)
+            .expect("Unable to inspect entry");
+
+        assert_eq!(inspect.state, EntryInspectState::Live);
+        let name = inspect.attrs.get("name").expect("name not present");
+        let description = inspect
+            .attrs
+            .get("description")
+            .expect("description not present");
+        assert_eq!(description.values, vec!["inspected".to_string()]);
+        // The description was changed after the entry was created.
+        assert!(description.changed_at > name.changed_at);
+        assert_eq!(name.changed_at.as_ref(), Some(&inspect.state_at));
+
+        // Only system administrators may inspect entries.
+        let anon = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_ANONYMOUS)
+            .map(Identity::from_impersonate_entry_readonly)
+            .expect("Unable to get anonymous");
+        assert_eq!(
+            idms_prox_read.inspect_entry(&anon, grp_uuid),
+            Err(OperationError::AccessDenied)
+        );
+        drop(idms_prox_read);
+
+        // Recycled entries can still be inspected.
+        let ct = ct + Duration::from_secs(1);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert!(idms_prox_write
+            .qs_write
+            .internal_delete_uuid(grp_uuid)
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let inspect = idms_prox_read
+            .inspect_entry(&admin, grp_uuid)
+            .expect("Unable to inspect entry");
+        assert_eq!(inspect.state, EntryInspectState::Recycled);
+    }
+}
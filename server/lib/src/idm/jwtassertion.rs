@@ -0,0 +1,258 @@
+//! Service accounts may register public keys, and then authenticate by presenting a jwt that
+//! is signed by one of those keys, rather than a long lived api token. The server issues a
+//! nonce when the authentication begins, and the assertion must contain that nonce, which
+//! binds it to a single authentication and prevents it from being replayed.
+//!
+//! ref <https://www.rfc-editor.org/rfc/rfc7523>
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use compact_jwt::{JwsCompact, JwsEs256Verifier, JwsVerifier};
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// The only assertion algorithm we accept. This matches the default signing algorithm of our
+/// own tokens.
+pub(crate) const JWT_ASSERTION_ALG: &str = "ES256";
+
+#[derive(Deserialize)]
+struct JwtAssertionHeader {
+    alg: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JwtAssertionClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    exp: i64,
+    iat: i64,
+    nonce: String,
+}
+
+/// Parse a public key in PEM format, returning it in DER form for storage. The key must be an
+/// ECDSA P-256 key, as that is the only algorithm we accept assertions with.
+pub(crate) fn jwt_public_key_from_pem(public_key_pem: &str) -> Result<Vec<u8>, OperationError> {
+    let public_key = PKey::public_key_from_pem(public_key_pem.as_bytes()).map_err(|err| {
+        admin_error!(?err, "Unable to parse jwt public key");
+        OperationError::InvalidAttribute("The public key is not valid PEM".to_string())
+    })?;
+
+    let is_p256 = public_key
+        .ec_key()
+        .ok()
+        .and_then(|ec_key| ec_key.group().curve_name())
+        == Some(Nid::X9_62_PRIME256V1);
+
+    if !is_p256 {
+        admin_error!("The jwt public key is not an ECDSA P-256 key");
+        return Err(OperationError::InvalidAttribute(
+            "The public key must be an ECDSA P-256 key".to_string(),
+        ));
+    }
+
+    public_key.public_key_to_der().map_err(|err| {
+        admin_error!(?err, "Unable to serialise jwt public key");
+        OperationError::CryptographyError
+    })
+}
+
+/// Verify that an assertion was signed by one of the keys of the account, and was created for
+/// this authentication. The issuer and subject must be one of the names of the account, such as
+/// its name, spn or uuid. On success, the label of the key that signed the assertion is returned.
+pub(crate) fn jwt_assertion_verify(
+    assertion: &str,
+    keys: &BTreeMap<String, Vec<u8>>,
+    subjects: &[String],
+    audience: &Url,
+    nonce: &str,
+    ct: Duration,
+) -> Result<String, &'static str> {
+    let jws = JwsCompact::from_str(assertion).map_err(|err| {
+        security_info!(?err, "jwt assertion is not a valid jws");
+        "assertion is not a valid jws"
+    })?;
+
+    // The header is checked before the signature, as the signature algorithm is selected
+    // from it.
+    let header = assertion
+        .split('.')
+        .next()
+        .and_then(|header| URL_SAFE_NO_PAD.decode(header).ok())
+        .and_then(|header| serde_json::from_slice::<JwtAssertionHeader>(&header).ok())
+        .ok_or_else(|| {
+            security_info!("jwt assertion header is invalid");
+            "assertion header is invalid"
+        })?;
+
+    if header.alg != JWT_ASSERTION_ALG {
+        security_info!(alg = %header.alg, "jwt assertion algorithm is not supported");
+        return Err("assertion algorithm is not supported");
+    }
+
+    // Accounts only have a handful of keys, so each is tried in turn rather than relying on
+    // the client to name the key that it signed with.
+    let (label, claims) = keys
+        .iter()
+        .find_map(|(label, der)| {
+            JwsEs256Verifier::from_es256_der(der)
+                .and_then(|verifier| verifier.verify(&jws))
+                .and_then(|jws| jws.from_json::<JwtAssertionClaims>())
+                .map(|claims| (label.clone(), claims))
+                .ok()
+        })
+        .ok_or_else(|| {
+            security_info!("jwt assertion was not signed by a key of the account");
+            "assertion was not signed by a key of the account"
+        })?;
+
+    let names_account = |name: &str| subjects.iter().any(|subject| subject == name);
+
+    if !names_account(&claims.iss) || !names_account(&claims.sub) {
+        security_info!(iss = %claims.iss, sub = %claims.sub, "jwt assertion was created for a different account");
+        return Err("assertion was created for a different account");
+    }
+
+    let aud_valid = Url::parse(&claims.aud)
+        .map(|aud| aud.origin() == audience.origin())
+        .unwrap_or_default();
+
+    if !aud_valid {
+        security_info!(aud = %claims.aud, %audience, "jwt assertion was created for a different server");
+        return Err("assertion was created for a different server");
+    }
+
+    if claims.nonce != nonce {
+        security_info!("jwt assertion was created for a different authentication");
+        return Err("assertion was created for a different authentication");
+    }
+
+    let now = ct.as_secs() as i64;
+    let max_lifetime = JWT_ASSERTION_MAX_LIFETIME as i64;
+    if claims.exp <= now || claims.exp > now + max_lifetime || claims.iat > now + max_lifetime {
+        security_info!(exp = %claims.exp, iat = %claims.iat, %now, "jwt assertion is not within its validity window");
+        return Err("assertion is expired or not yet valid");
+    }
+
+    Ok(label)
+}
+
+/// Create an assertion as a service account would.
+#[cfg(test)]
+pub(crate) fn test_jwt_assertion(
+    signer: &compact_jwt::JwsEs256Signer,
+    spn: &str,
+    audience: &str,
+    nonce: &str,
+    ct: Duration,
+) -> String {
+    use compact_jwt::{jws::JwsBuilder, JwsSigner};
+
+    let now = ct.as_secs() as i64;
+    let claims = JwtAssertionClaims {
+        iss: spn.to_string(),
+        sub: spn.to_string(),
+        aud: audience.to_string(),
+        exp: now + 60,
+        iat: now,
+        nonce: nonce.to_string(),
+    };
+
+    let jws = JwsBuilder::into_json(&claims)
+        .map(|builder| builder.build())
+        .expect("Unable to build assertion");
+
+    signer
+        .sign(&jws)
+        .expect("Unable to sign assertion")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{jwt_assertion_verify, jwt_public_key_from_pem, test_jwt_assertion};
+    use crate::prelude::*;
+    use compact_jwt::{JwsEs256Signer, JwsSignerToVerifier};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use std::collections::BTreeMap;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+    const TEST_SPN: &str = "test_service@example.com";
+
+    fn test_keys(signer: &JwsEs256Signer) -> BTreeMap<String, Vec<u8>> {
+        let der = signer
+            .get_verifier()
+            .and_then(|verifier| verifier.public_key_to_der())
+            .expect("Unable to export key");
+        BTreeMap::from([("deploy".to_string(), der)])
+    }
+
+    #[test]
+    fn test_jwt_assertion_verify() {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let origin = Url::parse("https://idm.example.com").expect("Invalid origin");
+        let subjects = vec![TEST_SPN.to_string(), Uuid::new_v4().to_string()];
+        let signer = JwsEs256Signer::generate_es256().expect("Unable to create signer");
+        let keys = test_keys(&signer);
+
+        let assertion =
+            test_jwt_assertion(&signer, TEST_SPN, "https://idm.example.com/", "nonce", ct);
+        assert_eq!(
+            jwt_assertion_verify(&assertion, &keys, &subjects, &origin, "nonce", ct),
+            Ok("deploy".to_string())
+        );
+
+        // Created for a different authentication.
+        assert!(jwt_assertion_verify(&assertion, &keys, &subjects, &origin, "other", ct).is_err());
+
+        // Expired.
+        let later = ct + Duration::from_secs(120);
+        assert!(
+            jwt_assertion_verify(&assertion, &keys, &subjects, &origin, "nonce", later).is_err()
+        );
+
+        // For a different account or server.
+        let other_subjects = vec!["other@example.com".to_string()];
+        assert!(
+            jwt_assertion_verify(&assertion, &keys, &other_subjects, &origin, "nonce", ct).is_err()
+        );
+        let other_origin = Url::parse("https://other.example.com").expect("Invalid origin");
+        assert!(
+            jwt_assertion_verify(&assertion, &keys, &subjects, &other_origin, "nonce", ct).is_err()
+        );
+
+        // Signed by a key that is not registered.
+        let other_signer = JwsEs256Signer::generate_es256().expect("Unable to create signer");
+        let assertion = test_jwt_assertion(
+            &other_signer,
+            TEST_SPN,
+            "https://idm.example.com",
+            "nonce",
+            ct,
+        );
+        assert!(jwt_assertion_verify(&assertion, &keys, &subjects, &origin, "nonce", ct).is_err());
+    }
+
+    #[test]
+    fn test_jwt_public_key_from_pem() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).expect("Invalid curve");
+        let key = EcKey::generate(&group).expect("Unable to generate key");
+        let public_key_pem = key.public_key_to_pem().expect("Unable to export key");
+        let public_key_pem = String::from_utf8(public_key_pem).expect("Invalid pem");
+        assert!(jwt_public_key_from_pem(&public_key_pem).is_ok());
+
+        let group = EcGroup::from_curve_name(Nid::SECP384R1).expect("Invalid curve");
+        let key = EcKey::generate(&group).expect("Unable to generate key");
+        let public_key_pem = key.public_key_to_pem().expect("Unable to export key");
+        let public_key_pem = String::from_utf8(public_key_pem).expect("Invalid pem");
+        assert!(jwt_public_key_from_pem(&public_key_pem).is_err());
+
+        assert!(jwt_public_key_from_pem("not a key").is_err());
+    }
+}
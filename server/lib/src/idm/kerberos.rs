@@ -0,0 +1,438 @@
+//! A bridge that issues Kerberos service tickets to the accounts that log in to enrolled hosts,
+//! so that legacy services such as NFS and SMB can be used without deploying a KDC. There is no
+//! ticket granting service - instead the host requests a ticket for each service on behalf of
+//! the account once the account has been authorised to log in to the host. The long term keys
+//! of services are derived from the kerberos secret of the domain key object, so they are
+//! replaced whenever the domain keys are rotated.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use kanidm_lib_crypto::kerberos::{
+    kerberos_keytab, KerberosPrincipal, KRB5_ENCTYPE_AES256_CTS_HMAC_SHA1_96,
+};
+use kanidm_proto::v1::{KerberosKeytab, KerberosTicket};
+use time::OffsetDateTime;
+
+use crate::idm::account::Account;
+use crate::idm::audit::AuditEvent;
+use crate::idm::group::load_account_policy;
+use crate::idm::hbac::{check_login_host, load_unix_hbac_policy};
+use crate::idm::server::IdmServerProxyWriteTransaction;
+use crate::prelude::*;
+
+pub struct HostKerberosKeytabEvent {
+    // The enrolled host, authenticated with its credential.
+    pub ident: Identity,
+}
+
+pub struct HostKerberosTicketEvent {
+    // The enrolled host, authenticated with its credential.
+    pub ident: Identity,
+    // The account that has logged in to the host.
+    pub account_id: String,
+    // The service principal name that the ticket is for.
+    pub service: String,
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    /// Ensure that the domain key object is able to derive kerberos service keys. The secret
+    /// is created on first use.
+    fn kerberos_assert(&mut self) -> Result<(), OperationError> {
+        let domain_entry = self.qs_write.internal_search_uuid(UUID_DOMAIN_INFO)?;
+
+        if domain_entry.attribute_equality(Attribute::Class, &EntryClass::KeyObjectKerberos.into())
+        {
+            return Ok(());
+        }
+
+        debug!("Adding kerberos secret to domain key object");
+
+        self.qs_write.internal_modify_uuid(
+            UUID_DOMAIN_INFO,
+            &ModifyList::new_append(Attribute::Class, EntryClass::KeyObjectKerberos.into()),
+        )?;
+
+        // Load the new secret into the key providers.
+        self.qs_write.reload()
+    }
+
+    /// The kerberos realm is the domain name in upper case, as is the convention.
+    fn kerberos_realm(&self) -> String {
+        self.qs_write.get_domain_name().to_uppercase()
+    }
+
+    /// Only an enrolled host, authenticated with its host credential, may use the bridge.
+    fn kerberos_host_entry(
+        &mut self,
+        ident: &Identity,
+    ) -> Result<Arc<EntrySealedCommitted>, OperationError> {
+        let Some(host_uuid) = ident.get_uuid() else {
+            error!("Only hosts may use the kerberos bridge");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let host_entry = self.qs_write.internal_search_uuid(host_uuid)?;
+
+        if !host_entry.attribute_equality(Attribute::Class, &EntryClass::Host.into()) {
+            error!("Only hosts may use the kerberos bridge");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let session_id = ident.get_session_id();
+        let is_host_credential = host_entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .and_then(|api_tokens| api_tokens.get(&session_id))
+            .map(|token| token.label == HOST_CREDENTIAL_LABEL)
+            .unwrap_or(false);
+
+        if !is_host_credential {
+            error!("Host did not authenticate with a host credential");
+            return Err(OperationError::AccessDenied);
+        }
+
+        Ok(host_entry)
+    }
+
+    /// The keytab of the service principals of the requesting host. Tickets issued before a
+    /// rotation remain valid until they expire, so the keys derived from retained secrets are
+    /// included.
+    #[instrument(level = "debug", skip_all)]
+    pub fn host_kerberos_keytab(
+        &mut self,
+        ev: &HostKerberosKeytabEvent,
+    ) -> Result<KerberosKeytab, OperationError> {
+        let host_entry = self.kerberos_host_entry(&ev.ident)?;
+
+        self.kerberos_assert()?;
+
+        let realm = self.kerberos_realm();
+        let key_object = self.qs_write.get_domain_key_object_handle()?;
+
+        let mut principals = Vec::new();
+        let mut entries = Vec::new();
+
+        for service in host_entry
+            .get_ava_iter_iutf8(Attribute::KerberosServicePrincipal)
+            .into_iter()
+            .flatten()
+        {
+            let Some(principal) = KerberosPrincipal::service(service, &realm) else {
+                warn!(?service, "Ignoring invalid kerberos service principal name");
+                continue;
+            };
+
+            for service_key in key_object.kerberos_service_keys(&principal)? {
+                entries.push((principal.clone(), service_key));
+            }
+
+            principals.push(principal.to_string());
+        }
+
+        Ok(KerberosKeytab {
+            realm,
+            principals,
+            keytab: kerberos_keytab(&entries),
+        })
+    }
+
+    /// Issue a ticket for a service to an account that has logged in to the requesting host.
+    /// The account must be permitted to log in to the host, and the service must belong to an
+    /// enrolled host of the domain.
+    #[instrument(level = "debug", skip_all)]
+    pub fn host_kerberos_ticket(
+        &mut self,
+        ev: &HostKerberosTicketEvent,
+        ct: Duration,
+    ) -> Result<KerberosTicket, OperationError> {
+        let host_entry = self.kerberos_host_entry(&ev.ident)?;
+
+        let host_name = host_entry
+            .get_ava_single_iname(Attribute::Name)
+            .ok_or(OperationError::MissingAttribute(Attribute::Name))?
+            .to_string();
+
+        let account_uuid = self.qs_write.name_to_uuid(&ev.account_id)?;
+        let account_entry = self.qs_write.internal_search_uuid(account_uuid)?;
+
+        if !account_entry.attribute_equality(Attribute::Class, &EntryClass::PosixAccount.into()) {
+            security_info!(account_id = %ev.account_id, "Only posix accounts may be issued kerberos tickets");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let account = Account::try_from_entry_rw(&account_entry, &mut self.qs_write)?;
+
+        if !account.is_within_valid_time(ct) {
+            security_info!(spn = %account.spn, "Account is expired or not yet valid");
+            return Err(OperationError::AccessDenied);
+        }
+
+        // The same decisions that are made when the account logs in to the host apply.
+        let account_policy = load_account_policy(&account_entry, &mut self.qs_write)?;
+        if !check_login_host(&account_policy, &ev.ident, &mut self.qs_write)? {
+            security_info!(spn = %account.spn, %host_name, "Account policy does not allow login to this host");
+            return Err(OperationError::AccessDenied);
+        }
+
+        if let Some(hbac) = load_unix_hbac_policy(&account, &mut self.qs_write)? {
+            if !hbac.is_allowed(&host_name, None) {
+                security_info!(spn = %account.spn, %host_name, "Host based access control denies login to this host");
+                return Err(OperationError::AccessDenied);
+            }
+        }
+
+        let realm = self.kerberos_realm();
+
+        let Some(service) = KerberosPrincipal::service(&ev.service, &realm) else {
+            error!(service = ?ev.service, "Invalid kerberos service principal name");
+            return Err(OperationError::InvalidRequestState);
+        };
+
+        // Only services of the domain have keys, so a ticket for any other service is useless.
+        if !self.qs_write.internal_exists(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Host.into()),
+            f_eq(
+                Attribute::KerberosServicePrincipal,
+                PartialValue::new_iutf8(&ev.service)
+            )
+        ])))? {
+            security_info!(service = ?ev.service, "Kerberos service principal does not exist");
+            return Err(OperationError::NoMatchingEntries);
+        }
+
+        // The ticket can't outlive the account.
+        let mut end_time = ct + Duration::from_secs(KERBEROS_TICKET_VALIDITY);
+        if let Some(expire) = account.expire {
+            let expire = Duration::from_secs(expire.unix_timestamp().max(0) as u64);
+            end_time = end_time.min(expire);
+        }
+
+        self.kerberos_assert()?;
+
+        let client = KerberosPrincipal::user(&account.name, &realm);
+
+        let credential = self
+            .qs_write
+            .get_domain_key_object_handle()?
+            .kerberos_ticket_issue(&client, &service, ct, end_time)?;
+
+        security_info!(spn = %account.spn, %service, "Issued kerberos ticket");
+
+        self.queue_activity_event(AuditEvent::KerberosTicketIssued {
+            source: ev.ident.source().clone().into(),
+            uuid: account.uuid,
+            spn: account.spn.clone(),
+            host: host_name,
+            service: service.to_string(),
+            end_time: OffsetDateTime::UNIX_EPOCH + end_time,
+            time: OffsetDateTime::UNIX_EPOCH + ct,
+        });
+
+        Ok(KerberosTicket {
+            realm,
+            client: credential.client.to_string(),
+            service: credential.service.to_string(),
+            enctype: KRB5_ENCTYPE_AES256_CTS_HMAC_SHA1_96,
+            session_key: credential.session_key,
+            auth_time: credential.auth_time,
+            end_time: credential.end_time,
+            ticket: credential.ticket,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kanidm_lib_crypto::kerberos::{kerberos_decrypt, KerberosPrincipal};
+
+    use super::{HostKerberosKeytabEvent, HostKerberosTicketEvent};
+    use crate::idm::host::{GenerateHostJoinTokenEvent, HostEnrollEvent};
+    use crate::idm::server::IdmServerProxyWriteTransaction;
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+    const UUID_TEST_HOST: Uuid = uuid!("7c7c7c7c-1d2e-4f3a-9b8c-0d1e2f3a4b5c");
+    const UUID_TEST_ACCOUNT: Uuid = uuid!("8d8d8d8d-2e3f-4a5b-8c9d-1e2f3a4b5c6d");
+
+    fn enroll_host(
+        idms_prox_write: &mut IdmServerProxyWriteTransaction<'_>,
+        ct: Duration,
+    ) -> Identity {
+        let e_host: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Class, EntryClass::Host.to_value()),
+            (Attribute::Name, Value::new_iname("files")),
+            (Attribute::Uuid, Value::Uuid(UUID_TEST_HOST)),
+            (Attribute::DisplayName, Value::new_utf8s("files")),
+            (
+                Attribute::KerberosServicePrincipal,
+                Value::new_iutf8("nfs/files.example.com")
+            )
+        );
+
+        let e_account: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::PosixAccount.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(UUID_TEST_ACCOUNT)),
+            (Attribute::Description, Value::new_utf8s("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson"))
+        );
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e_host, e_account])
+            .expect("unable to create entries");
+
+        let join_token = idms_prox_write
+            .host_generate_join_token(
+                &GenerateHostJoinTokenEvent {
+                    ident: Identity::from_internal(),
+                    target: UUID_TEST_HOST,
+                    ttl: None,
+                },
+                ct,
+            )
+            .expect("Failed to generate join token");
+
+        let credential = idms_prox_write
+            .host_enroll(
+                &HostEnrollEvent {
+                    name: "files".to_string(),
+                    join_token,
+                },
+                ct,
+            )
+            .expect("Failed to enroll host");
+
+        idms_prox_write
+            .validate_client_auth_info_to_ident(credential.into(), ct)
+            .expect("Unable to authenticate with host credential")
+    }
+
+    /// The cipher of the encrypted part is the final octet string of the ticket.
+    fn ticket_cipher(ticket: &[u8]) -> Option<&[u8]> {
+        (0..ticket.len()).rev().find_map(|pos| {
+            if ticket[pos] != 0x04 {
+                return None;
+            }
+            let (header, len) = match *ticket.get(pos + 1)? {
+                len @ 0..=0x7f => (2, len as usize),
+                0x81 => (3, *ticket.get(pos + 2)? as usize),
+                0x82 => (
+                    4,
+                    u16::from_be_bytes([*ticket.get(pos + 2)?, *ticket.get(pos + 3)?]) as usize,
+                ),
+                _ => return None,
+            };
+            (pos + header + len == ticket.len()).then(|| &ticket[pos + header..])
+        })
+    }
+
+    #[idm_test]
+    async fn test_idm_kerberos_ticket_bridge(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let ident = enroll_host(&mut idms_prox_write, ct);
+
+        let keytab = idms_prox_write
+            .host_kerberos_keytab(&HostKerberosKeytabEvent {
+                ident: ident.clone(),
+            })
+            .expect("Failed to get keytab");
+
+        let realm = idms_prox_write.qs_write.get_domain_name().to_uppercase();
+        assert_eq!(keytab.realm, realm);
+        assert_eq!(
+            keytab.principals,
+            vec![format!("nfs/files.example.com@{}", realm)]
+        );
+        // The keytab version, followed by the entry of the key.
+        assert_eq!(&keytab.keytab[..2], &[0x05, 0x02]);
+
+        let ticket = idms_prox_write
+            .host_kerberos_ticket(
+                &HostKerberosTicketEvent {
+                    ident: ident.clone(),
+                    account_id: "testperson".to_string(),
+                    service: "nfs/files.example.com".to_string(),
+                },
+                ct,
+            )
+            .expect("Failed to issue ticket");
+
+        assert_eq!(ticket.client, format!("testperson@{}", realm));
+        assert_eq!(ticket.auth_time, TEST_CURRENT_TIME);
+        assert_eq!(
+            ticket.end_time,
+            TEST_CURRENT_TIME + KERBEROS_TICKET_VALIDITY
+        );
+
+        // The service key that is in the keytab decrypts the ticket, and the encrypted part
+        // carries the session key that was given to the client.
+        let service = KerberosPrincipal::service("nfs/files.example.com", &realm)
+            .expect("Invalid service principal");
+        let service_keys = idms_prox_write
+            .qs_write
+            .get_domain_key_object_handle()
+            .expect("No domain key object")
+            .kerberos_service_keys(&service)
+            .expect("No kerberos service keys");
+        assert_eq!(service_keys.len(), 1);
+
+        let enc_part = ticket_cipher(&ticket.ticket).expect("Ticket has no cipher");
+        let released = kerberos_decrypt(&service_keys[0].key, 2, enc_part)
+            .expect("Service key did not decrypt ticket");
+        assert!(released
+            .windows(ticket.session_key.len())
+            .any(|w| w == ticket.session_key.as_slice()));
+
+        // Only services of the domain have keys.
+        assert_eq!(
+            idms_prox_write.host_kerberos_ticket(
+                &HostKerberosTicketEvent {
+                    ident: ident.clone(),
+                    account_id: "testperson".to_string(),
+                    service: "cifs/other.example.com".to_string(),
+                },
+                ct,
+            ),
+            Err(OperationError::NoMatchingEntries)
+        );
+
+        // Only posix accounts may be issued tickets.
+        assert_eq!(
+            idms_prox_write.host_kerberos_ticket(
+                &HostKerberosTicketEvent {
+                    ident: ident.clone(),
+                    account_id: "admin".to_string(),
+                    service: "nfs/files.example.com".to_string(),
+                },
+                ct,
+            ),
+            Err(OperationError::AccessDenied)
+        );
+
+        // Only hosts may use the bridge.
+        let admin_entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_ADMIN)
+            .expect("Can't access admin entry.");
+        assert_eq!(
+            idms_prox_write.host_kerberos_keytab(&HostKerberosKeytabEvent {
+                ident: Identity::from_impersonate_entry_readwrite(admin_entry),
+            }),
+            Err(OperationError::AccessDenied)
+        );
+
+        idms_prox_write.commit().expect("failed to commit");
+    }
+}
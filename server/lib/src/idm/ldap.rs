@@ -8,6 +8,9 @@ use std::str::FromStr;
 use compact_jwt::JwsCompact;
 use kanidm_proto::constants::*;
 use kanidm_proto::internal::{ApiToken, UserAuthToken};
+use ldap3_proto::proto::{
+    LdapExtendedRequest, LdapExtendedResponse, LdapOp, LdapPasswordModifyRequest, LdapResult,
+};
 use ldap3_proto::simple::*;
 use regex::{Regex, RegexBuilder};
 use std::net::IpAddr;
@@ -15,10 +18,19 @@ use tracing::trace;
 use uuid::Uuid;
 
 use crate::event::SearchEvent;
-use crate::idm::event::{LdapApplicationAuthEvent, LdapAuthEvent, LdapTokenAuthEvent};
+use crate::idm::event::{
+    LdapApplicationAuthEvent, LdapAuthEvent, LdapTokenAuthEvent, UnixPasswordChangeEvent,
+};
 use crate::idm::server::{IdmServer, IdmServerAuthTransaction, IdmServerTransaction};
+use crate::metrics::LdapOperation;
 use crate::prelude::*;
 
+/// The OID of the RFC 3062 password modify extended operation.
+pub const LDAP_EXOP_PASSWORD_MODIFY: &str = "1.3.6.1.4.1.4203.1.11.1";
+
+/// The OID of the Active Directory matching rule that matches nested group memberships.
+pub const LDAP_MATCHING_RULE_IN_CHAIN: &str = "1.2.840.113556.1.4.1941";
+
 // Clippy doesn't like Bind here. But proto needs unboxed ldapmsg,
 // and ldapboundtoken is moved. Really, it's not too bad, every message here is pretty sucky.
 #[allow(clippy::large_enum_variant)]
@@ -143,7 +155,10 @@ impl LdapServer {
                 },
                 LdapPartialAttribute {
                     atype: "supportedextension".to_string(),
-                    vals: vec!["1.3.6.1.4.1.4203.1.11.3".as_bytes().to_vec()],
+                    vals: vec![
+                        LDAP_EXOP_PASSWORD_MODIFY.as_bytes().to_vec(),
+                        "1.3.6.1.4.1.4203.1.11.3".as_bytes().to_vec(),
+                    ],
                 },
                 LdapPartialAttribute {
                     atype: "supportedfeatures".to_string(),
@@ -409,6 +424,7 @@ impl LdapServer {
                         self.basedn.as_str(),
                         all_attrs,
                         &l_attrs,
+                        ct,
                     )
                     // if okay, wrap in a ldap msg.
                     .map(|r| sr.gen_result_entry(r))
@@ -612,6 +628,14 @@ impl LdapServer {
     ) -> Result<LdapResponseState, OperationError> {
         let source = Source::Ldaps(ip_addr);
 
+        idms.metrics().record_ldap_operation(match &server_op {
+            ServerOps::SimpleBind(_) => LdapOperation::Bind,
+            ServerOps::Search(_) => LdapOperation::Search,
+            ServerOps::Unbind(_) => LdapOperation::Unbind,
+            ServerOps::Compare(_) => LdapOperation::Compare,
+            ServerOps::Whoami(_) => LdapOperation::Whoami,
+        });
+
         match server_op {
             ServerOps::SimpleBind(sbr) => self
                 .do_bind(idms, sbr.dn.as_str(), sbr.pw.as_str())
@@ -707,6 +731,143 @@ impl LdapServer {
         } // end match server op
     }
 
+    /// Handle an RFC 3062 password modify extended operation. This changes the unix password
+    /// of the bound account, and requires the current password so that an unattended session
+    /// can't be used to take over the account. The new password is subject to the same quality
+    /// checks as any other password change.
+    pub async fn do_password_modify(
+        &self,
+        idms: &IdmServer,
+        msgid: i32,
+        ler: &LdapExtendedRequest,
+        uat: Option<LdapBoundToken>,
+        ip_addr: IpAddr,
+    ) -> LdapResponseState {
+        idms.metrics().record_ldap_operation(LdapOperation::PasswordModify);
+
+        let (code, message) = match LdapPasswordModifyRequest::try_from(ler) {
+            Ok(pmr) => {
+                self.password_modify(idms, &pmr, uat.as_ref(), Source::Ldaps(ip_addr))
+                    .await
+            }
+            Err(_) => (
+                LdapResultCode::ProtocolError,
+                "Invalid password modify request".to_string(),
+            ),
+        };
+
+        LdapResponseState::Respond(LdapMsg {
+            msgid,
+            op: LdapOp::ExtendedResponse(LdapExtendedResponse {
+                res: LdapResult {
+                    code,
+                    matcheddn: "".to_string(),
+                    message,
+                    referral: Vec::with_capacity(0),
+                },
+                name: None,
+                value: None,
+            }),
+            ctrl: Vec::with_capacity(0),
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn password_modify(
+        &self,
+        idms: &IdmServer,
+        pmr: &LdapPasswordModifyRequest,
+        uat: Option<&LdapBoundToken>,
+        source: Source,
+    ) -> (LdapResultCode, String) {
+        // Only an account that bound with its own credentials can change its password. Api
+        // tokens and application passwords are not able to.
+        let target = match uat.map(|uat| &uat.effective_session) {
+            Some(LdapSession::UnixBind(uuid)) if *uuid != UUID_ANONYMOUS => *uuid,
+            Some(LdapSession::UserAuthToken(uat)) => uat.uuid,
+            _ => {
+                security_info!("LDAP Password Modify requires an account bind");
+                return (LdapResultCode::InsufficentAccessRights, "".to_string());
+            }
+        };
+
+        let (Some(old_password), Some(new_password)) =
+            (pmr.old_password.as_deref(), pmr.new_password.as_deref())
+        else {
+            // We don't generate passwords on behalf of the client.
+            return (
+                LdapResultCode::UnwillingToPerform,
+                "The old and new passwords are required".to_string(),
+            );
+        };
+
+        let ct = duration_from_epoch_now();
+
+        match self
+            .password_modify_verify(idms, target, pmr.user_identity.as_deref(), old_password, ct)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => return (LdapResultCode::InvalidCredentials, "".to_string()),
+            Err(e) => return password_modify_err_to_ldapresultcode(e),
+        }
+
+        let result = async {
+            let mut idms_prox_write = idms.proxy_write(ct).await?;
+            let entry = idms_prox_write.qs_write.internal_search_uuid(target)?;
+            let ident = Identity::from_ldap_password_modify(entry, source);
+            let pce = UnixPasswordChangeEvent::from_parts(ident, target, new_password.to_string())?;
+            idms_prox_write.set_unix_account_password(&pce)?;
+            idms_prox_write.commit()
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                security_info!(?target, "LDAP Password Modify success");
+                (LdapResultCode::Success, "".to_string())
+            }
+            Err(e) => {
+                security_info!(?target, err = ?e, "LDAP Password Modify failure");
+                password_modify_err_to_ldapresultcode(e)
+            }
+        }
+    }
+
+    /// Check that the identity the client asked to change is the bound account, and that the
+    /// old password is correct.
+    async fn password_modify_verify(
+        &self,
+        idms: &IdmServer,
+        target: Uuid,
+        user_identity: Option<&str>,
+        old_password: &str,
+        ct: Duration,
+    ) -> Result<bool, OperationError> {
+        let mut idm_auth = idms.auth().await?;
+
+        if let Some(user_identity) = user_identity {
+            let usr = self
+                .binddnre
+                .captures(user_identity)
+                .filter(|captures| captures.name("app").is_none())
+                .and_then(|captures| captures.name("val"))
+                .ok_or(OperationError::NoMatchingEntries)?;
+
+            if idm_auth.qs_read.name_to_uuid(usr.as_str())? != target {
+                security_info!("LDAP Password Modify may only change the bound account");
+                return Err(OperationError::AccessDenied);
+            }
+        }
+
+        let lae = LdapAuthEvent::from_parts(target, old_password.to_string())?;
+        let result = idm_auth.auth_ldap(&lae, ct).await?;
+
+        idm_auth.commit()?;
+
+        Ok(result.is_some())
+    }
+
     async fn bind_target_from_bind_dn(
         &self,
         idm_auth: &mut IdmServerAuthTransaction<'_>,
@@ -789,10 +950,36 @@ fn operationerr_to_ldapresultcode(e: OperationError) -> (LdapResultCode, String)
         OperationError::SchemaViolation(se) => {
             (LdapResultCode::UnwillingToPerform, format!("{se:?}"))
         }
+        OperationError::KG006WhitePagesRateLimited => (
+            LdapResultCode::Busy,
+            "white pages rate limit reached".to_string(),
+        ),
+        OperationError::KG008MaintenanceModeReadOnly => (
+            LdapResultCode::Unavailable,
+            "server is in maintenance mode and is read only".to_string(),
+        ),
         e => (LdapResultCode::Other, format!("{e:?}")),
     }
 }
 
+fn password_modify_err_to_ldapresultcode(e: OperationError) -> (LdapResultCode, String) {
+    match e {
+        OperationError::AccessDenied | OperationError::NotAuthenticated => {
+            (LdapResultCode::InsufficentAccessRights, "".to_string())
+        }
+        OperationError::NoMatchingEntries => (LdapResultCode::NoSuchObject, "".to_string()),
+        OperationError::PasswordQuality(feedback) => (
+            LdapResultCode::ConstraintViolation,
+            format!("Password does not meet the password policy {feedback:?}"),
+        ),
+        OperationError::MissingClass(_) | OperationError::SystemProtectedObject => (
+            LdapResultCode::UnwillingToPerform,
+            "Account does not have a unix password".to_string(),
+        ),
+        e => operationerr_to_ldapresultcode(e),
+    }
+}
+
 #[inline]
 pub(crate) fn ldap_all_vattrs() -> Vec<String> {
     vec![
@@ -812,6 +999,7 @@ pub(crate) fn ldap_all_vattrs() -> Vec<String> {
         ATTR_UIDNUMBER.to_string(),
         ATTR_UID.to_string(),
         ATTR_GECOS.to_string(),
+        ATTR_TELEPHONE_NUMBER.to_string(),
     ]
 }
 
@@ -842,6 +1030,7 @@ pub(crate) fn ldap_vattr_map(input: &str) -> Option<&str> {
         ATTR_OBJECTCLASS => Some(ATTR_CLASS),
         ATTR_LDAP_SSHPUBLICKEY => Some(ATTR_SSH_PUBLICKEY), // no-underscore -> underscore
         ATTR_UIDNUMBER => Some(ATTR_GIDNUMBER),             // yes this is intentional
+        ATTR_TELEPHONE_NUMBER => Some(ATTR_PHONE),
         _ => None,
     }
 }
@@ -860,12 +1049,15 @@ mod tests {
     use hashbrown::HashSet;
     use kanidm_proto::internal::ApiToken;
     use ldap3_proto::proto::{
-        LdapFilter, LdapMsg, LdapOp, LdapResultCode, LdapSearchScope, LdapSubstringFilter,
+        LdapFilter, LdapMatchingRuleAssertion, LdapMsg, LdapOp, LdapPasswordModifyRequest,
+        LdapResultCode, LdapSearchScope, LdapSubstringFilter,
     };
     use ldap3_proto::simple::*;
+    use std::net::IpAddr;
 
-    use super::{LdapServer, LdapSession};
+    use super::{LdapServer, LdapSession, LDAP_MATCHING_RULE_IN_CHAIN};
     use crate::idm::application::GenerateApplicationPasswordEvent;
+    use crate::idm::delayed::DelayedAction;
     use crate::idm::event::{LdapApplicationAuthEvent, UnixPasswordChangeEvent};
     use crate::idm::serviceaccount::GenerateApiTokenEvent;
 
@@ -1913,7 +2105,7 @@ mod tests {
     #[idm_test]
     async fn test_ldap_token_privilege_granting(
         idms: &IdmServer,
-        _idms_delayed: &IdmServerDelayed,
+        idms_delayed: &mut IdmServerDelayed,
     ) {
         // Setup the ldap server
         let ldaps = LdapServer::new(idms).await.expect("failed to start ldap");
@@ -2149,6 +2341,11 @@ mod tests {
             }
             _ => panic!("Oh no"),
         };
+
+        // Each use of the api token is recorded.
+        while let Ok(da) = idms_delayed.try_recv() {
+            assert!(matches!(da, DelayedAction::ApiTokenUse(_)));
+        }
     }
 
     #[idm_test]
@@ -2517,6 +2714,109 @@ mod tests {
         };
     }
 
+    #[idm_test]
+    async fn test_ldap_group_compat(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
+        let ldaps = LdapServer::new(idms).await.expect("failed to start ldap");
+
+        let acct_uuid = Uuid::new_v4();
+        let inner_uuid = Uuid::new_v4();
+        let outer_uuid = Uuid::new_v4();
+
+        {
+            let e1 = entry_init!(
+                (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Name, Value::new_iname("testaccount1")),
+                (Attribute::Uuid, Value::Uuid(acct_uuid)),
+                (Attribute::DisplayName, Value::new_utf8s("testaccount1"))
+            );
+
+            let e2 = entry_init!(
+                (Attribute::Class, EntryClass::Group.to_value()),
+                (Attribute::Name, Value::new_iname("testgroup_inner")),
+                (Attribute::Uuid, Value::Uuid(inner_uuid)),
+                (Attribute::Member, Value::Refer(acct_uuid))
+            );
+
+            let e3 = entry_init!(
+                (Attribute::Class, EntryClass::Group.to_value()),
+                (Attribute::Name, Value::new_iname("testgroup_outer")),
+                (Attribute::Uuid, Value::Uuid(outer_uuid)),
+                (Attribute::Member, Value::Refer(inner_uuid))
+            );
+
+            let mut server_txn = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+            assert!(server_txn
+                .qs_write
+                .internal_create(vec![e1, e2, e3])
+                .and_then(|_| server_txn.commit())
+                .is_ok());
+        }
+
+        let anon_t = ldaps.do_bind(idms, "", "").await.unwrap().unwrap();
+
+        let member_dn = "spn=testaccount1@example.com,dc=example,dc=com";
+
+        let member_sr = SearchRequest {
+            msgid: 1,
+            base: "dc=example,dc=com".to_string(),
+            scope: LdapSearchScope::Subtree,
+            filter: LdapFilter::And(vec![
+                LdapFilter::Equality(Attribute::Class.to_string(), "group".to_string()),
+                LdapFilter::Equality(Attribute::Member.to_string(), member_dn.to_string()),
+            ]),
+            attrs: vec![LDAP_ATTR_NAME.to_string()],
+        };
+
+        let in_chain_sr = SearchRequest {
+            msgid: 2,
+            base: "dc=example,dc=com".to_string(),
+            scope: LdapSearchScope::Subtree,
+            filter: LdapFilter::Extensible(LdapMatchingRuleAssertion {
+                matching_rule: Some(LDAP_MATCHING_RULE_IN_CHAIN.to_string()),
+                type_: Some(Attribute::Member.to_string()),
+                match_value: member_dn.to_string(),
+                dn_attributes: false,
+            }),
+            attrs: vec![LDAP_ATTR_NAME.to_string()],
+        };
+
+        // Only the direct membership matches by default.
+        let r1 = ldaps
+            .do_search(idms, &member_sr, &anon_t, Source::Internal)
+            .await
+            .unwrap();
+        assert_eq!(r1.len(), 2);
+
+        assert!(ldaps
+            .do_search(idms, &in_chain_sr, &anon_t, Source::Internal)
+            .await
+            .is_err());
+
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_DOMAIN_INFO,
+                &ModifyList::new_purge_and_set(Attribute::LdapGroupCompat, Value::Bool(true))
+            )
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        // Now the nested group matches too.
+        let r1 = ldaps
+            .do_search(idms, &member_sr, &anon_t, Source::Internal)
+            .await
+            .unwrap();
+        assert_eq!(r1.len(), 3);
+
+        let r1 = ldaps
+            .do_search(idms, &in_chain_sr, &anon_t, Source::Internal)
+            .await
+            .unwrap();
+        assert_eq!(r1.len(), 3);
+    }
+
     #[idm_test]
     async fn test_ldap_compare_request(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
         let ldaps = LdapServer::new(idms).await.expect("failed to start ldap");
@@ -2747,4 +3047,237 @@ mod tests {
         assert_eq!(invalid_res, Err(OperationError::ResourceLimit));
         assert!(valid_res.is_ok());
     }
+
+    #[idm_test]
+    async fn test_ldap_password_modify(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
+        let ldaps = LdapServer::new(idms).await.expect("failed to start ldap");
+
+        const NEW_PASSWORD: &str = "eiChae8ohmu3Eif0ahph🦀";
+        let acct_uuid = uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930");
+
+        {
+            let e1 = entry_init!(
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::PosixAccount.to_value()),
+                (Attribute::Name, Value::new_iname("testperson1")),
+                (Attribute::Uuid, Value::Uuid(acct_uuid)),
+                (Attribute::GidNumber, Value::Uint32(12345)),
+                (Attribute::Description, Value::new_utf8s("testperson1")),
+                (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+            );
+
+            let mut server_txn = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+            assert!(server_txn.qs_write.internal_create(vec![e1]).is_ok());
+            let pce = UnixPasswordChangeEvent::new_internal(acct_uuid, TEST_PASSWORD);
+            assert!(server_txn.set_unix_account_password(&pce).is_ok());
+            assert!(server_txn.commit().is_ok());
+        }
+
+        let anon_t = ldaps.do_bind(idms, "", "").await.unwrap().unwrap();
+        let user_t = ldaps
+            .do_bind(idms, "testperson1", TEST_PASSWORD)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let pmr = |user_identity: Option<&str>, old: &str, new: &str| LdapPasswordModifyRequest {
+            user_identity: user_identity.map(str::to_string),
+            old_password: Some(old.to_string()),
+            new_password: Some(new.to_string()),
+        };
+
+        // Anonymous can't change a password.
+        let (code, _) = ldaps
+            .password_modify(
+                idms,
+                &pmr(None, TEST_PASSWORD, NEW_PASSWORD),
+                Some(&anon_t),
+                Source::Internal,
+            )
+            .await;
+        assert_eq!(code, LdapResultCode::InsufficentAccessRights);
+
+        // Only the bound account can be changed.
+        let (code, _) = ldaps
+            .password_modify(
+                idms,
+                &pmr(Some("admin"), TEST_PASSWORD, NEW_PASSWORD),
+                Some(&user_t),
+                Source::Internal,
+            )
+            .await;
+        assert_eq!(code, LdapResultCode::InsufficentAccessRights);
+
+        // The old password must be correct.
+        let (code, _) = ldaps
+            .password_modify(
+                idms,
+                &pmr(None, "incorrect", NEW_PASSWORD),
+                Some(&user_t),
+                Source::Internal,
+            )
+            .await;
+        assert_eq!(code, LdapResultCode::InvalidCredentials);
+
+        // The new password must meet the password policy.
+        let (code, _) = ldaps
+            .password_modify(
+                idms,
+                &pmr(None, TEST_PASSWORD, "password"),
+                Some(&user_t),
+                Source::Internal,
+            )
+            .await;
+        assert_eq!(code, LdapResultCode::ConstraintViolation);
+
+        let (code, _) = ldaps
+            .password_modify(
+                idms,
+                &pmr(
+                    Some("name=testperson1,dc=example,dc=com"),
+                    TEST_PASSWORD,
+                    NEW_PASSWORD,
+                ),
+                Some(&user_t),
+                Source::Internal,
+            )
+            .await;
+        assert_eq!(code, LdapResultCode::Success);
+
+        assert!(ldaps
+            .do_bind(idms, "testperson1", TEST_PASSWORD)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(ldaps
+            .do_bind(idms, "testperson1", NEW_PASSWORD)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[idm_test]
+    async fn test_ldap_white_pages(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
+        let ldaps = LdapServer::new(idms).await.expect("failed to start ldap");
+
+        let sr = SearchRequest {
+            msgid: 1,
+            base: "dc=example,dc=com".to_string(),
+            scope: LdapSearchScope::Subtree,
+            filter: LdapFilter::Equality(Attribute::Name.to_string(), "testperson1".to_string()),
+            attrs: vec![LDAP_ATTR_NAME, LDAP_ATTR_MAIL, ATTR_TELEPHONE_NUMBER]
+                .into_iter()
+                .map(|s| s.to_string())
+                .collect(),
+        };
+
+        let has_white_pages = |r1: &[LdapMsg]| match &r1[0].op {
+            LdapOp::SearchResultEntry(lsre) => lsre
+                .attributes
+                .iter()
+                .any(|a| a.atype == LDAP_ATTR_MAIL || a.atype == ATTR_TELEPHONE_NUMBER),
+            _ => panic!("Oh no"),
+        };
+
+        let trusted = Source::Ldaps(IpAddr::from([10, 1, 1, 1]));
+        let untrusted = Source::Ldaps(IpAddr::from([203, 0, 113, 1]));
+
+        {
+            let e1 = entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Name, Value::new_iname("testperson1")),
+                (
+                    Attribute::Mail,
+                    Value::EmailAddress("testperson1@example.com".to_string(), true)
+                ),
+                (Attribute::Phone, Value::new_utf8s("+61 2 5550 1234")),
+                (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+            );
+
+            let mut server_txn = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+            assert!(server_txn
+                .qs_write
+                .internal_create(vec![e1])
+                .and_then(|_| server_txn.commit())
+                .is_ok());
+        }
+
+        let anon_t = ldaps.do_bind(idms, "", "").await.unwrap().unwrap();
+
+        // The white pages are not shown until anonymous is granted them.
+        let r1 = ldaps
+            .do_search(idms, &sr, &anon_t, untrusted.clone())
+            .await
+            .unwrap();
+        assert_eq!(r1.len(), 2);
+        assert!(!has_white_pages(&r1));
+
+        let mut server_txn = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_WHITE_PAGES_READERS,
+                &ModifyList::new_append(Attribute::Member, Value::Refer(UUID_ANONYMOUS))
+            )
+            .and_then(|_| server_txn.commit())
+            .is_ok());
+
+        let r1 = ldaps
+            .do_search(idms, &sr, &anon_t, untrusted.clone())
+            .await
+            .unwrap();
+        assert_eq!(r1.len(), 2);
+        match &r1[0].op {
+            LdapOp::SearchResultEntry(lsre) => {
+                assert_entry_contains!(
+                    lsre,
+                    "spn=testperson1@example.com,dc=example,dc=com",
+                    (Attribute::Name, "testperson1"),
+                    (Attribute::Mail, "testperson1@example.com"),
+                    (ATTR_TELEPHONE_NUMBER, "+61 2 5550 1234")
+                );
+            }
+            _ => panic!("Oh no"),
+        };
+
+        // Restrict the white pages to a network, and to one search each window.
+        let mut server_txn = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn
+            .qs_write
+            .internal_modify_uuid(
+                UUID_DOMAIN_INFO,
+                &ModifyList::new_list(vec![
+                    Modify::Present(
+                        Attribute::WhitePagesTrustedNetwork,
+                        Value::new_utf8s("10.0.0.0/8")
+                    ),
+                    Modify::Present(Attribute::WhitePagesRateLimit, Value::Uint32(1)),
+                ])
+            )
+            .and_then(|_| server_txn.commit())
+            .is_ok());
+
+        // Outside of the network the entry is found, but without the white pages.
+        let r1 = ldaps
+            .do_search(idms, &sr, &anon_t, untrusted)
+            .await
+            .unwrap();
+        assert_eq!(r1.len(), 2);
+        assert!(!has_white_pages(&r1));
+
+        let r1 = ldaps
+            .do_search(idms, &sr, &anon_t, trusted.clone())
+            .await
+            .unwrap();
+        assert_eq!(r1.len(), 2);
+        assert!(has_white_pages(&r1));
+
+        assert_eq!(
+            ldaps.do_search(idms, &sr, &anon_t, trusted).await.err(),
+            Some(OperationError::KG006WhitePagesRateLimited)
+        );
+    }
 }
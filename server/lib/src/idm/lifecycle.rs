@@ -0,0 +1,394 @@
+//! Account lifecycle policies give the members of a group a limited lifetime. Accounts without
+//! an expiry are set to expire a period after they were created, and accounts that have been
+//! expired for long enough are moved to the recycle bin. These policies are enforced by a
+//! scheduled task rather than as changes are made, so that accounts which join a group are
+//! handled the same way as those that were already members.
+//!
+//! Time-bound group memberships are handled in the same way, with members removed from
+//! their groups once their membership lapses.
+
+use std::collections::BTreeSet;
+
+use time::OffsetDateTime;
+
+use crate::idm::audit::AuditEvent;
+use crate::idm::group::load_account_policy;
+use crate::idm::server::IdmServerProxyWriteTransaction;
+use crate::prelude::*;
+
+impl IdmServerProxyWriteTransaction<'_> {
+    /// Apply the lifecycle policies of groups to their members. The audit events describing
+    /// each change are returned so that they can be submitted once the transaction commits.
+    #[instrument(level = "debug", skip_all)]
+    pub fn apply_account_lifecycle_policies(
+        &mut self,
+        ct: Duration,
+    ) -> Result<Vec<AuditEvent>, OperationError> {
+        let policy_groups = self.qs_write.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::AccountPolicy.into()),
+            f_or!([
+                f_pres(Attribute::AccountLifetime),
+                f_pres(Attribute::AccountArchiveDelay)
+            ])
+        ])))?;
+
+        if policy_groups.is_empty() {
+            return Ok(Vec::with_capacity(0));
+        }
+
+        let member_filter = policy_groups
+            .iter()
+            .map(|group| f_eq(Attribute::MemberOf, PartialValue::Refer(group.get_uuid())))
+            .collect();
+
+        // Builtin accounts such as admin must never be expired by a policy, and synchronised
+        // accounts have their expiry managed by the sync provider.
+        let accounts = self.qs_write.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Account.into()),
+            f_or(member_filter),
+            f_andnot(f_eq(Attribute::Class, EntryClass::Builtin.into())),
+            f_andnot(f_pres(Attribute::SyncParentUuid))
+        ])))?;
+
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+        let mut events = Vec::new();
+        let mut archive = BTreeSet::new();
+
+        for account in accounts.iter() {
+            let account_policy = load_account_policy(account, &mut self.qs_write)?;
+
+            let uuid = account.get_uuid();
+            let spn = account
+                .get_ava_single_proto_string(Attribute::Spn)
+                .unwrap_or_else(|| uuid.to_string());
+
+            match account.get_ava_single_datetime(Attribute::AccountExpire) {
+                None => {
+                    let Some(lifetime) = account_policy.account_lifetime() else {
+                        continue;
+                    };
+
+                    let Some(created) = account
+                        .get_ava_set(Attribute::CreatedAtCid)
+                        .and_then(|vs| vs.to_cid_single())
+                    else {
+                        warn!(?uuid, "Account has no creation time, unable to set expiry");
+                        continue;
+                    };
+
+                    let expiry = created.ts + lifetime;
+
+                    self.qs_write.internal_modify_uuid(
+                        uuid,
+                        &ModifyList::new_purge_and_set(
+                            Attribute::AccountExpire,
+                            Value::new_datetime_epoch(expiry),
+                        ),
+                    )?;
+
+                    debug!(?uuid, "Set account expiry from lifecycle policy");
+
+                    events.push(AuditEvent::AccountLifecycleExpirySet {
+                        uuid,
+                        spn,
+                        expiry: OffsetDateTime::UNIX_EPOCH + expiry,
+                        time: now,
+                    });
+                }
+                Some(expiry) => {
+                    let Some(delay) = account_policy.account_archive_delay() else {
+                        continue;
+                    };
+
+                    if now < expiry + delay {
+                        continue;
+                    }
+
+                    debug!(?uuid, "Archiving expired account from lifecycle policy");
+
+                    archive.insert(uuid);
+                    events.push(AuditEvent::AccountLifecycleArchived {
+                        uuid,
+                        spn,
+                        expiry,
+                        time: now,
+                    });
+                }
+            }
+        }
+
+        if !archive.is_empty() {
+            self.qs_write.internal_delete(&filter!(f_or(
+                archive
+                    .into_iter()
+                    .map(|uuid| f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)))
+                    .collect()
+            )))?;
+        }
+
+        Ok(events)
+    }
+
+    /// Remove the members of groups whose membership has lapsed. Memberof is only
+    /// recalculated as entries change, so this is what removes lapsed groups from the
+    /// memberof of their members. Returns the number of memberships that were removed.
+    #[instrument(level = "debug", skip_all)]
+    pub fn purge_lapsed_group_members(&mut self, ct: Duration) -> Result<usize, OperationError> {
+        let groups = self.qs_write.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Group.into()),
+            f_pres(Attribute::MemberValidUntil)
+        ])))?;
+
+        let mut purged = 0;
+
+        for group in groups.iter() {
+            let expired = group.get_members_expired(ct);
+            if expired.is_empty() {
+                continue;
+            }
+
+            debug!(group_uuid = ?group.get_uuid(), ?expired, "Removing lapsed group members");
+
+            // The valid until times of the removed members are cleaned up by memberof.
+            let modlist = ModifyList::new_list(
+                expired
+                    .iter()
+                    .map(|member| Modify::Removed(Attribute::Member, PartialValue::Refer(*member)))
+                    .collect(),
+            );
+
+            self.qs_write
+                .internal_modify_uuid(group.get_uuid(), &modlist)?;
+
+            purged += expired.len();
+        }
+
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::idm::account::Account;
+    use crate::idm::audit::AuditEvent;
+    use crate::prelude::*;
+    use kanidm_proto::v1::GroupMemberValidUntil;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+    const LIFETIME: u32 = 86400 * 90;
+    const ARCHIVE_DELAY: u32 = 86400 * 30;
+
+    #[idm_test]
+    async fn test_idm_account_lifecycle_policy(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let group_uuid = Uuid::new_v4();
+        let member_uuid = Uuid::new_v4();
+        let other_uuid = Uuid::new_v4();
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let member = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("contractor")),
+            (Attribute::Uuid, Value::Uuid(member_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("Contractor"))
+        );
+
+        let other = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("employee")),
+            (Attribute::Uuid, Value::Uuid(other_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("Employee"))
+        );
+
+        let group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Class, EntryClass::AccountPolicy.to_value()),
+            (Attribute::Name, Value::new_iname("contractors")),
+            (Attribute::Uuid, Value::Uuid(group_uuid)),
+            (Attribute::Member, Value::Refer(member_uuid)),
+            (Attribute::AccountLifetime, Value::Uint32(LIFETIME)),
+            (Attribute::AccountArchiveDelay, Value::Uint32(ARCHIVE_DELAY))
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![member, other, group])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        // Members without an expiry are set to expire after the lifetime.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let events = idms_prox_write
+            .apply_account_lifecycle_policies(ct)
+            .expect("Unable to apply lifecycle policies");
+        assert!(idms_prox_write.commit().is_ok());
+
+        let expected_expiry =
+            time::OffsetDateTime::UNIX_EPOCH + ct + Duration::from_secs(LIFETIME as u64);
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            AuditEvent::AccountLifecycleExpirySet { uuid, expiry, .. }
+                if *uuid == member_uuid && *expiry == expected_expiry
+        ));
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let member = idms_prox_read
+            .qs_read
+            .internal_search_uuid(member_uuid)
+            .expect("Unable to find member");
+        assert_eq!(
+            member.get_ava_single_datetime(Attribute::AccountExpire),
+            Some(expected_expiry)
+        );
+        let other = idms_prox_read
+            .qs_read
+            .internal_search_uuid(other_uuid)
+            .expect("Unable to find other account");
+        assert!(other
+            .get_ava_single_datetime(Attribute::AccountExpire)
+            .is_none());
+        drop(idms_prox_read);
+
+        // Once expired, the account is kept until the archive delay has passed.
+        let ct = ct + Duration::from_secs(LIFETIME as u64 + 1);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let events = idms_prox_write
+            .apply_account_lifecycle_policies(ct)
+            .expect("Unable to apply lifecycle policies");
+        assert!(events.is_empty());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let ct = ct + Duration::from_secs(ARCHIVE_DELAY as u64);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let events = idms_prox_write
+            .apply_account_lifecycle_policies(ct)
+            .expect("Unable to apply lifecycle policies");
+        assert!(idms_prox_write.commit().is_ok());
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            AuditEvent::AccountLifecycleArchived { uuid, .. } if *uuid == member_uuid
+        ));
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        assert_eq!(
+            idms_prox_read
+                .qs_read
+                .internal_search_uuid(member_uuid)
+                .unwrap_err(),
+            OperationError::NoMatchingEntries
+        );
+        assert!(idms_prox_read
+            .qs_read
+            .internal_search_uuid(other_uuid)
+            .is_ok());
+    }
+
+    #[idm_test]
+    async fn test_idm_group_member_valid_until(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let group_uuid = Uuid::new_v4();
+        let member_uuid = Uuid::new_v4();
+        let valid_until = time::OffsetDateTime::UNIX_EPOCH + ct + Duration::from_secs(3600);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let member = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("oncall")),
+            (Attribute::Uuid, Value::Uuid(member_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("On Call"))
+        );
+
+        let group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("break_glass")),
+            (Attribute::Uuid, Value::Uuid(group_uuid)),
+            (Attribute::Member, Value::Refer(member_uuid)),
+            (
+                Attribute::MemberValidUntil,
+                Value::new_utf8(
+                    GroupMemberValidUntil {
+                        member: member_uuid,
+                        valid_until,
+                    }
+                    .to_string()
+                )
+            )
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![member, group])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        // Until the membership lapses, nothing is removed.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert_eq!(
+            idms_prox_write
+                .purge_lapsed_group_members(ct)
+                .expect("Unable to purge lapsed group members"),
+            0
+        );
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(member_uuid)
+            .expect("Unable to find member");
+        assert!(entry.attribute_equality(Attribute::MemberOf, &PartialValue::Refer(group_uuid)));
+
+        let mut account = Account::try_from_entry_rw(&entry, &mut idms_prox_write.qs_write)
+            .expect("Unable to load account");
+        account.remove_lapsed_groups(ct);
+        assert!(account.groups.iter().any(|g| *g.uuid() == group_uuid));
+
+        // Once lapsed, the group is no longer part of the account's claims, even before
+        // the membership is removed.
+        let ct = ct + Duration::from_secs(3600);
+        account.remove_lapsed_groups(ct);
+        assert!(!account.groups.iter().any(|g| *g.uuid() == group_uuid));
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert_eq!(
+            idms_prox_write
+                .purge_lapsed_group_members(ct)
+                .expect("Unable to purge lapsed group members"),
+            1
+        );
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let entry = idms_prox_read
+            .qs_read
+            .internal_search_uuid(member_uuid)
+            .expect("Unable to find member");
+        assert!(!entry.attribute_equality(Attribute::MemberOf, &PartialValue::Refer(group_uuid)));
+
+        let group = idms_prox_read
+            .qs_read
+            .internal_search_uuid(group_uuid)
+            .expect("Unable to find group");
+        assert!(!group.attribute_pres(Attribute::Member));
+        assert!(!group.attribute_pres(Attribute::MemberValidUntil));
+    }
+}
@@ -0,0 +1,661 @@
+//! Groups may accept requests from accounts to join them. A request is held as an entry until
+//! one of the entry managers of the group approves or denies it. The entry is kept once it is
+//! decided, so that there is a record of who was granted membership, who granted it, and how
+//! long it was granted for.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use kanidm_proto::v1::{
+    GroupMemberValidUntil, GroupMembershipRequest, GroupMembershipRequestStatus,
+    GroupMembershipRequests,
+};
+use time::OffsetDateTime;
+
+use crate::idm::audit::AuditEvent;
+use crate::idm::notification::SecurityNotification;
+use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction};
+use crate::prelude::*;
+
+pub struct GroupMembershipRequestEvent {
+    // Who initiated this? This is the account that will become a member.
+    pub ident: Identity,
+    pub group: Uuid,
+    // Why the account needs to be a member, shown to the approvers.
+    pub justification: Option<String>,
+}
+
+pub struct GroupMembershipDecisionEvent {
+    // Who initiated this? This must be an entry manager of the requested group.
+    pub ident: Identity,
+    pub request_id: Uuid,
+    pub approve: bool,
+    // When an approved membership lapses.
+    pub valid_until: Option<OffsetDateTime>,
+}
+
+fn request_status(entry: &EntrySealedCommitted) -> Option<GroupMembershipRequestStatus> {
+    entry
+        .get_ava_single_iutf8(Attribute::MembershipRequestStatus)
+        .and_then(|status| GroupMembershipRequestStatus::from_str(status).ok())
+}
+
+fn status_value(status: GroupMembershipRequestStatus) -> Value {
+    Value::new_iutf8(&status.to_string())
+}
+
+/// If this identity may approve requests to join the group. These are the entry managers of
+/// the group, or the members of the group that manages it.
+fn is_group_approver(ident: &Identity, group: &EntrySealedCommitted) -> bool {
+    group
+        .get_ava_refer(Attribute::EntryManagedBy)
+        .into_iter()
+        .flatten()
+        .any(|manager| ident.get_uuid() == Some(*manager) || ident.is_memberof(*manager))
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    fn spn_of(&mut self, uuid: Uuid) -> Option<String> {
+        self.qs_read
+            .internal_search_uuid(uuid)
+            .ok()
+            .and_then(|entry| entry.get_ava_single_proto_string(Attribute::Spn))
+    }
+
+    fn membership_request_from_entry(
+        &mut self,
+        entry: &EntrySealedCommitted,
+    ) -> Option<GroupMembershipRequest> {
+        // The group or the requester may have been deleted since the request was made.
+        let group = self.spn_of(entry.get_ava_single_refer(Attribute::MembershipRequestGroup)?)?;
+        let requester = self.spn_of(entry.get_ava_single_refer(Attribute::MembershipRequester)?)?;
+        let decided_by = entry
+            .get_ava_single_refer(Attribute::MembershipRequestDecidedBy)
+            .and_then(|uuid| self.spn_of(uuid));
+        let requested_at = entry
+            .get_ava_set(Attribute::CreatedAtCid)
+            .and_then(|vs| vs.to_cid_single())
+            .map(|cid| OffsetDateTime::UNIX_EPOCH + cid.ts)?;
+
+        Some(GroupMembershipRequest {
+            uuid: entry.get_uuid(),
+            group,
+            requester,
+            justification: entry
+                .get_ava_single_utf8(Attribute::Description)
+                .map(str::to_string),
+            status: request_status(entry)?,
+            decided_by,
+            valid_until: entry.get_ava_single_datetime(Attribute::MembershipRequestValidUntil),
+            requested_at,
+        })
+    }
+
+    /// List the requests that this identity has made to join groups, and the pending requests
+    /// that it is able to approve.
+    #[instrument(level = "debug", skip_all)]
+    pub fn list_group_membership_requests(
+        &mut self,
+        ident: &Identity,
+    ) -> Result<GroupMembershipRequests, OperationError> {
+        let Some(ident_uuid) = ident.get_uuid() else {
+            error!("Only accounts may list group membership requests");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let requested_entries = self.qs_read.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::GroupMembershipRequest.into()),
+            f_eq(
+                Attribute::MembershipRequester,
+                PartialValue::Refer(ident_uuid)
+            )
+        ])))?;
+
+        let mut requested = requested_entries
+            .iter()
+            .filter_map(|entry| self.membership_request_from_entry(entry))
+            .collect::<Vec<_>>();
+
+        // The most recent requests first.
+        requested.sort_unstable_by(|a, b| b.requested_at.cmp(&a.requested_at));
+
+        let pending_entries = self.qs_read.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::GroupMembershipRequest.into()),
+            f_eq(
+                Attribute::MembershipRequestStatus,
+                PartialValue::new_iutf8(&GroupMembershipRequestStatus::Pending.to_string())
+            )
+        ])))?;
+
+        // Many requests are likely to be for the same few groups.
+        let mut approvable: BTreeMap<Uuid, bool> = BTreeMap::new();
+        let mut awaiting_approval = Vec::new();
+
+        for entry in pending_entries.iter() {
+            let Some(group_uuid) = entry.get_ava_single_refer(Attribute::MembershipRequestGroup)
+            else {
+                continue;
+            };
+
+            let is_approver = match approvable.get(&group_uuid) {
+                Some(is_approver) => *is_approver,
+                None => {
+                    let is_approver = self
+                        .qs_read
+                        .internal_search_uuid(group_uuid)
+                        .is_ok_and(|group| is_group_approver(ident, &group));
+                    approvable.insert(group_uuid, is_approver);
+                    is_approver
+                }
+            };
+
+            if is_approver {
+                if let Some(request) = self.membership_request_from_entry(entry) {
+                    awaiting_approval.push(request);
+                }
+            }
+        }
+
+        // The oldest requests first, as they have waited the longest.
+        awaiting_approval.sort_unstable_by(|a, b| a.requested_at.cmp(&b.requested_at));
+
+        Ok(GroupMembershipRequests {
+            awaiting_approval,
+            requested,
+        })
+    }
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    /// The accounts that are able to approve requests to join this group.
+    fn group_approvers(
+        &mut self,
+        group: &EntrySealedCommitted,
+    ) -> Result<BTreeSet<Uuid>, OperationError> {
+        let Some(managers) = group.get_ava_refer(Attribute::EntryManagedBy) else {
+            return Ok(BTreeSet::new());
+        };
+
+        let filter = filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Account.into()),
+            f_or(
+                managers
+                    .iter()
+                    .flat_map(|manager| {
+                        [
+                            f_eq(Attribute::Uuid, PartialValue::Uuid(*manager)),
+                            f_eq(Attribute::MemberOf, PartialValue::Refer(*manager)),
+                        ]
+                    })
+                    .collect()
+            )
+        ]));
+
+        Ok(self
+            .qs_write
+            .internal_search(filter)?
+            .iter()
+            .map(|entry| entry.get_uuid())
+            .collect())
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub fn request_group_membership(
+        &mut self,
+        gmre: &GroupMembershipRequestEvent,
+        ct: Duration,
+    ) -> Result<Uuid, OperationError> {
+        let Some(requester) = gmre.ident.get_uuid() else {
+            error!("Only accounts may request to join a group");
+            return Err(OperationError::AccessDenied);
+        };
+
+        if gmre.ident.access_scope() != AccessScope::ReadWrite {
+            error!("Requesting to join a group requires a read-write session");
+            return Err(OperationError::AccessDenied);
+        }
+
+        self.qs_write.check_maintenance_mode(&gmre.ident)?;
+
+        let group = self.qs_write.internal_search_uuid(gmre.group)?;
+
+        if !group.attribute_equality(Attribute::Class, &EntryClass::Group.into())
+            || !group
+                .get_ava_single_bool(Attribute::MembershipRequestable)
+                .unwrap_or_default()
+        {
+            error!(group = %gmre.group, "Group does not accept membership requests");
+            return Err(OperationError::GR0001GroupMembershipNotRequestable);
+        }
+
+        let approvers = self.group_approvers(&group)?;
+
+        if approvers.is_empty() {
+            error!(group = %gmre.group, "Group has no entry managers to approve membership requests");
+            return Err(OperationError::GR0001GroupMembershipNotRequestable);
+        }
+
+        let pending = self.qs_write.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::GroupMembershipRequest.into()),
+            f_eq(
+                Attribute::MembershipRequestGroup,
+                PartialValue::Refer(gmre.group)
+            ),
+            f_eq(
+                Attribute::MembershipRequester,
+                PartialValue::Refer(requester)
+            ),
+            f_eq(
+                Attribute::MembershipRequestStatus,
+                PartialValue::new_iutf8(&GroupMembershipRequestStatus::Pending.to_string())
+            )
+        ])))?;
+
+        if !pending.is_empty()
+            || group.attribute_equality(Attribute::Member, &PartialValue::Refer(requester))
+        {
+            error!(group = %gmre.group, "Account is already a member of, or has requested to join, this group");
+            return Err(OperationError::GR0002GroupMembershipRequestExists);
+        }
+
+        let justification = gmre
+            .justification
+            .as_deref()
+            .map(str::trim)
+            .filter(|justification| !justification.is_empty());
+
+        let request_id = Uuid::new_v4();
+
+        let mut entry = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::GroupMembershipRequest.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(request_id)),
+            (Attribute::MembershipRequestGroup, Value::Refer(gmre.group)),
+            (Attribute::MembershipRequester, Value::Refer(requester)),
+            (
+                Attribute::MembershipRequestStatus,
+                status_value(GroupMembershipRequestStatus::Pending)
+            )
+        );
+
+        if let Some(justification) = justification {
+            entry.add_ava(Attribute::Description, Value::new_utf8s(justification));
+        }
+
+        // The request is created on behalf of the requester, who would otherwise have no
+        // access to create it.
+        self.qs_write.internal_create(vec![entry]).map_err(|err| {
+            error!(?err, "Failed to create group membership request");
+            err
+        })?;
+
+        let spn = self
+            .qs_write
+            .internal_search_uuid(requester)?
+            .get_ava_single_proto_string(Attribute::Spn)
+            .unwrap_or_else(|| requester.to_string());
+        let group_spn = group
+            .get_ava_single_proto_string(Attribute::Spn)
+            .unwrap_or_else(|| gmre.group.to_string());
+        let time = OffsetDateTime::UNIX_EPOCH + ct;
+
+        security_info!(%request_id, %spn, group = %group_spn, "Requested to join group");
+
+        for approver in approvers {
+            self.queue_notification(SecurityNotification::GroupMembershipRequested {
+                uuid: approver,
+                requester: spn.clone(),
+                group: group_spn.clone(),
+                justification: justification.map(str::to_string),
+                time,
+            });
+        }
+
+        self.submit_audit_event(AuditEvent::GroupMembershipRequested {
+            source: gmre.ident.source().clone().into(),
+            uuid: requester,
+            spn,
+            group: gmre.group,
+            request_id,
+            time,
+        });
+
+        Ok(request_id)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub fn decide_group_membership_request(
+        &mut self,
+        gmde: &GroupMembershipDecisionEvent,
+        ct: Duration,
+    ) -> Result<(), OperationError> {
+        let Some(approver) = gmde.ident.get_uuid() else {
+            error!("Only accounts may decide group membership requests");
+            return Err(OperationError::AccessDenied);
+        };
+
+        if gmde.ident.access_scope() != AccessScope::ReadWrite {
+            error!("Deciding a group membership request requires a read-write session");
+            return Err(OperationError::AccessDenied);
+        }
+
+        self.qs_write.check_maintenance_mode(&gmde.ident)?;
+
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+
+        let valid_until = gmde
+            .valid_until
+            .map(|valid_until| valid_until.to_offset(time::UtcOffset::UTC));
+
+        if valid_until.is_some_and(|valid_until| valid_until <= now) {
+            error!("A granted membership must be valid until a time in the future");
+            return Err(OperationError::InvalidRequestState);
+        }
+
+        let request = self.qs_write.internal_search_uuid(gmde.request_id)?;
+
+        if !request.attribute_equality(Attribute::Class, &EntryClass::GroupMembershipRequest.into())
+        {
+            error!(request_id = %gmde.request_id, "Entry is not a group membership request");
+            return Err(OperationError::NoMatchingEntries);
+        }
+
+        if request_status(&request) != Some(GroupMembershipRequestStatus::Pending) {
+            error!(request_id = %gmde.request_id, "Group membership request is already decided");
+            return Err(OperationError::GR0003GroupMembershipRequestDecided);
+        }
+
+        let (Some(group_uuid), Some(requester)) = (
+            request.get_ava_single_refer(Attribute::MembershipRequestGroup),
+            request.get_ava_single_refer(Attribute::MembershipRequester),
+        ) else {
+            error!(request_id = %gmde.request_id, "The group or requester of this request no longer exists");
+            return Err(OperationError::NoMatchingEntries);
+        };
+
+        let group = self.qs_write.internal_search_uuid(group_uuid)?;
+
+        if !is_group_approver(&gmde.ident, &group) {
+            error!(request_id = %gmde.request_id, "Only the entry managers of a group may decide requests to join it");
+            return Err(OperationError::AccessDenied);
+        }
+
+        if gmde.approve {
+            // Any existing valid until time of the requester is replaced, so that an approval
+            // without one grants a membership that doesn't lapse.
+            let mut mods: Vec<_> = group
+                .get_ava_set(Attribute::MemberValidUntil)
+                .and_then(|vs| vs.as_utf8_iter())
+                .into_iter()
+                .flatten()
+                .filter(|value| {
+                    GroupMemberValidUntil::from_str(value)
+                        .is_ok_and(|member_valid_until| member_valid_until.member == requester)
+                })
+                .map(|value| {
+                    Modify::Removed(Attribute::MemberValidUntil, PartialValue::new_utf8s(value))
+                })
+                .collect();
+
+            mods.push(Modify::Present(Attribute::Member, Value::Refer(requester)));
+
+            if let Some(valid_until) = valid_until {
+                mods.push(Modify::Present(
+                    Attribute::MemberValidUntil,
+                    Value::new_utf8(
+                        GroupMemberValidUntil {
+                            member: requester,
+                            valid_until,
+                        }
+                        .to_string(),
+                    ),
+                ));
+            }
+
+            // The membership is granted with the access of the approver, so that requests can't
+            // grant more than the approver could have granted directly.
+            self.qs_write
+                .impersonate_modify(
+                    // Filter as executed
+                    &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(group_uuid))),
+                    // Filter as intended (acp)
+                    &filter_all!(f_eq(Attribute::Uuid, PartialValue::Uuid(group_uuid))),
+                    &ModifyList::new_list(mods),
+                    &gmde.ident,
+                )
+                .map_err(|err| {
+                    error!(?err, "Failed to grant group membership");
+                    err
+                })?;
+        }
+
+        let status = if gmde.approve {
+            GroupMembershipRequestStatus::Approved
+        } else {
+            GroupMembershipRequestStatus::Denied
+        };
+
+        let mut mods = vec![
+            Modify::Purged(Attribute::MembershipRequestStatus),
+            Modify::Present(Attribute::MembershipRequestStatus, status_value(status)),
+            Modify::Present(
+                Attribute::MembershipRequestDecidedBy,
+                Value::Refer(approver),
+            ),
+        ];
+
+        if let Some(valid_until) = valid_until.filter(|_| gmde.approve) {
+            mods.push(Modify::Present(
+                Attribute::MembershipRequestValidUntil,
+                Value::DateTime(valid_until),
+            ));
+        }
+
+        self.qs_write
+            .internal_modify_uuid(gmde.request_id, &ModifyList::new_list(mods))
+            .map_err(|err| {
+                error!(?err, "Failed to record group membership request decision");
+                err
+            })?;
+
+        security_info!(request_id = %gmde.request_id, %status, "Decided group membership request");
+
+        self.submit_audit_event(AuditEvent::GroupMembershipRequestDecided {
+            source: gmde.ident.source().clone().into(),
+            actor: approver,
+            uuid: requester,
+            group: group_uuid,
+            request_id: gmde.request_id,
+            approved: gmde.approve,
+            valid_until: valid_until.filter(|_| gmde.approve),
+            time: now,
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GroupMembershipDecisionEvent, GroupMembershipRequestEvent};
+    use crate::prelude::*;
+    use kanidm_proto::v1::GroupMembershipRequestStatus;
+    use time::OffsetDateTime;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    const APPROVER_UUID: Uuid = uuid!("3c1d7a2e-5b8f-4e0a-9c6d-1f2e3a4b5c6d");
+    const REQUESTER_UUID: Uuid = uuid!("8e2f4b6a-1c3d-4e5f-a7b8-9c0d1e2f3a4b");
+    const MANAGERS_UUID: Uuid = uuid!("5a6b7c8d-9e0f-4a1b-8c2d-3e4f5a6b7c8d");
+    const GROUP_UUID: Uuid = uuid!("1b2c3d4e-5f6a-4b7c-9d8e-0f1a2b3c4d5e");
+
+    fn person(uuid: Uuid, name: &str) -> EntryInitNew {
+        entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname(name)),
+            (Attribute::Uuid, Value::Uuid(uuid)),
+            (Attribute::DisplayName, Value::new_utf8s(name))
+        )
+    }
+
+    #[idm_test]
+    async fn test_idm_group_membership_request(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let valid_until = OffsetDateTime::UNIX_EPOCH + ct + Duration::from_secs(86400);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let managers = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("approvers")),
+            (Attribute::Uuid, Value::Uuid(MANAGERS_UUID)),
+            (Attribute::Member, Value::Refer(APPROVER_UUID))
+        );
+
+        let group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("production_access")),
+            (Attribute::Uuid, Value::Uuid(GROUP_UUID)),
+            (Attribute::EntryManagedBy, Value::Refer(MANAGERS_UUID)),
+            (Attribute::MembershipRequestable, Value::Bool(true))
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![
+                person(APPROVER_UUID, "approver"),
+                person(REQUESTER_UUID, "requester"),
+                managers,
+                group
+            ])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let requester = idms_prox_write
+            .qs_write
+            .internal_search_uuid(REQUESTER_UUID)
+            .expect("Unable to find requester");
+        let requester_ident = Identity::from_impersonate_entry_readwrite(requester);
+
+        let approver = idms_prox_write
+            .qs_write
+            .internal_search_uuid(APPROVER_UUID)
+            .expect("Unable to find approver");
+        let approver_ident = Identity::from_impersonate_entry_readwrite(approver);
+
+        // The managers group can't be requested, as it isn't requestable.
+        let gmre = GroupMembershipRequestEvent {
+            ident: requester_ident.clone(),
+            group: MANAGERS_UUID,
+            justification: None,
+        };
+        assert_eq!(
+            idms_prox_write.request_group_membership(&gmre, ct),
+            Err(OperationError::GR0001GroupMembershipNotRequestable)
+        );
+
+        let gmre = GroupMembershipRequestEvent {
+            ident: requester_ident.clone(),
+            group: GROUP_UUID,
+            justification: Some("On call this week".to_string()),
+        };
+        let request_id = idms_prox_write
+            .request_group_membership(&gmre, ct)
+            .expect("Unable to request group membership");
+
+        // Only one request may be pending at a time.
+        assert_eq!(
+            idms_prox_write.request_group_membership(&gmre, ct),
+            Err(OperationError::GR0002GroupMembershipRequestExists)
+        );
+
+        // The requester can't approve their own request.
+        let gmde = GroupMembershipDecisionEvent {
+            ident: requester_ident.clone(),
+            request_id,
+            approve: true,
+            valid_until: None,
+        };
+        assert_eq!(
+            idms_prox_write.decide_group_membership_request(&gmde, ct),
+            Err(OperationError::AccessDenied)
+        );
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let requests = idms_prox_read
+            .list_group_membership_requests(&approver_ident)
+            .expect("Unable to list requests");
+        assert!(requests.requested.is_empty());
+        assert_eq!(requests.awaiting_approval.len(), 1);
+        let request = &requests.awaiting_approval[0];
+        assert_eq!(request.uuid, request_id);
+        assert_eq!(request.requester, "requester@example.com");
+        assert_eq!(request.group, "production_access@example.com");
+        assert_eq!(request.justification.as_deref(), Some("On call this week"));
+        assert_eq!(request.status, GroupMembershipRequestStatus::Pending);
+
+        let requests = idms_prox_read
+            .list_group_membership_requests(&requester_ident)
+            .expect("Unable to list requests");
+        assert!(requests.awaiting_approval.is_empty());
+        assert_eq!(requests.requested.len(), 1);
+        drop(idms_prox_read);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let gmde = GroupMembershipDecisionEvent {
+            ident: approver_ident.clone(),
+            request_id,
+            approve: true,
+            valid_until: Some(valid_until),
+        };
+        assert!(idms_prox_write
+            .decide_group_membership_request(&gmde, ct)
+            .is_ok());
+
+        // Once decided, it can't be decided again.
+        assert_eq!(
+            idms_prox_write.decide_group_membership_request(&gmde, ct),
+            Err(OperationError::GR0003GroupMembershipRequestDecided)
+        );
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let group = idms_prox_read
+            .qs_read
+            .internal_search_uuid(GROUP_UUID)
+            .expect("Unable to find group");
+        assert!(group.attribute_equality(Attribute::Member, &PartialValue::Refer(REQUESTER_UUID)));
+        assert_eq!(
+            group.get_member_valid_until().get(&REQUESTER_UUID),
+            Some(&valid_until)
+        );
+
+        let requests = idms_prox_read
+            .list_group_membership_requests(&requester_ident)
+            .expect("Unable to list requests");
+        let request = &requests.requested[0];
+        assert_eq!(request.status, GroupMembershipRequestStatus::Approved);
+        assert_eq!(request.decided_by.as_deref(), Some("approver@example.com"));
+        assert_eq!(request.valid_until, Some(valid_until));
+
+        let requests = idms_prox_read
+            .list_group_membership_requests(&approver_ident)
+            .expect("Unable to list requests");
+        assert!(requests.awaiting_approval.is_empty());
+    }
+}
@@ -4,24 +4,56 @@
 //! is implemented.
 
 pub mod account;
+pub mod accountrecovery;
 pub(crate) mod accountpolicy;
-pub(crate) mod application;
+pub mod apitoken;
+pub mod application;
 pub(crate) mod applinks;
 pub mod audit;
+pub mod authmetrics;
 pub(crate) mod authsession;
+pub mod breakglass;
+pub(crate) mod changes;
 pub mod credupdatesession;
 pub mod delayed;
+pub mod dpop;
+pub mod emaillink;
 pub mod event;
+pub mod geoip;
 pub mod group;
+pub(crate) mod hbac;
+pub mod hook;
+pub mod host;
+pub(crate) mod hostgroup;
+pub(crate) mod inspect;
 pub mod identityverification;
+pub mod impersonation;
+pub(crate) mod jwtassertion;
+pub mod kerberos;
 pub mod ldap;
+pub(crate) mod lifecycle;
+pub mod membershiprequest;
+pub mod notification;
 pub mod oauth2;
+pub mod oauth2consent;
+pub mod oidcupstream;
+pub mod passwordexpiry;
 pub(crate) mod radius;
 pub(crate) mod reauth;
+pub mod report;
+pub mod saml2;
 pub mod scim;
+pub(crate) mod scimprovision;
 pub mod server;
 pub mod serviceaccount;
+pub mod session_binding;
+pub mod sessions;
+pub(crate) mod sshca;
+pub mod webhook;
+pub(crate) mod whitepages;
 
+use crate::idm::dpop::DPoPProof;
+use crate::idm::session_binding::SessionProof;
 use crate::server::identity::Source;
 use compact_jwt::JwsCompact;
 use kanidm_lib_crypto::{x509_cert::Certificate, Sha256Digest};
@@ -33,6 +65,19 @@ pub enum AuthState {
     Continue(Vec<AuthAllowed>),
     Denied(String),
     Success(Box<JwsCompact>, AuthIssueSession),
+    /// The password is correct but has expired. The token must be exchanged for a credential
+    /// update session to change it before a session is issued.
+    CredentialUpdateRequired(Box<JwsCompact>),
+}
+
+/// The category of an authentication denial. Unlike the reason of the denial, this is safe to show
+/// to the person authenticating since it doesn't reveal which of their credentials was incorrect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthDeniedKind {
+    CredentialsIncorrect,
+    AccountLocked,
+    AccountExpired,
+    PolicyDenied,
 }
 
 impl fmt::Debug for AuthState {
@@ -42,6 +87,9 @@ impl fmt::Debug for AuthState {
             AuthState::Continue(allow) => write!(f, "AuthState::Continue({allow:?})"),
             AuthState::Denied(reason) => write!(f, "AuthState::Denied({reason:?})"),
             AuthState::Success(_token, issue) => write!(f, "AuthState::Success({issue:?})"),
+            AuthState::CredentialUpdateRequired(_token) => {
+                write!(f, "AuthState::CredentialUpdateRequired")
+            }
         }
     }
 }
@@ -52,6 +100,12 @@ pub struct ClientAuthInfo {
     pub client_cert: Option<ClientCertInfo>,
     pub bearer_token: Option<JwsCompact>,
     pub basic_authz: Option<String>,
+    /// A DPoP proof of possession sent with the request.
+    pub dpop: Option<DPoPProof>,
+    /// A proof that the client holds the key that its session is bound to.
+    pub session_proof: Option<SessionProof>,
+    /// The user agent of the client, recorded against the sessions it creates.
+    pub user_agent: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -68,6 +122,9 @@ impl ClientAuthInfo {
             client_cert: None,
             bearer_token: None,
             basic_authz: None,
+            dpop: None,
+            session_proof: None,
+            user_agent: None,
         }
     }
 }
@@ -80,6 +137,9 @@ impl From<Source> for ClientAuthInfo {
             client_cert: None,
             bearer_token: None,
             basic_authz: None,
+            dpop: None,
+            session_proof: None,
+            user_agent: None,
         }
     }
 }
@@ -92,6 +152,9 @@ impl From<JwsCompact> for ClientAuthInfo {
             client_cert: None,
             bearer_token: Some(value),
             basic_authz: None,
+            dpop: None,
+            session_proof: None,
+            user_agent: None,
         }
     }
 }
@@ -104,6 +167,9 @@ impl From<ClientCertInfo> for ClientAuthInfo {
             client_cert: Some(value),
             bearer_token: None,
             basic_authz: None,
+            dpop: None,
+            session_proof: None,
+            user_agent: None,
         }
     }
 }
@@ -116,6 +182,9 @@ impl From<&str> for ClientAuthInfo {
             client_cert: None,
             bearer_token: None,
             basic_authz: Some(value.to_string()),
+            dpop: None,
+            session_proof: None,
+            user_agent: None,
         }
     }
 }
@@ -131,6 +200,9 @@ impl ClientAuthInfo {
             client_cert: None,
             bearer_token: None,
             basic_authz: Some(value),
+            dpop: None,
+            session_proof: None,
+            user_agent: None,
         }
     }
 }
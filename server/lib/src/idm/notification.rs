@@ -0,0 +1,434 @@
+//! Security notifications tell the owner of an account about events that affect it, such as
+//! their credentials changing. Unlike audit events these are not a record of activity, and
+//! are only produced once a consumer has subscribed to them. Delivery of the notification
+//! is the responsibility of that consumer.
+
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::time::Duration;
+
+use kanidm_proto::v1::SshPublicKeyExpiry;
+use time::OffsetDateTime;
+use tokio::sync::mpsc::UnboundedSender as Sender;
+
+use crate::idm::geoip::{GeoLocation, SessionAnomaly};
+use crate::idm::server::IdmServerProxyReadTransaction;
+use crate::prelude::*;
+use crate::server::identity::Source;
+
+/// A credential must be locked for at least this long before the account owner is notified.
+/// This excludes the short delays that are applied between failed attempts.
+pub(crate) const SOFTLOCK_NOTIFICATION_THRESHOLD: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityNotification {
+    /// A new session was created for this account.
+    SessionCreated {
+        uuid: Uuid,
+        source: Source,
+        /// Where the source is located, if a GeoIP database is loaded.
+        location: Option<GeoLocation>,
+        time: OffsetDateTime,
+    },
+    /// This account signed in from an unusual location.
+    UnusualSignIn {
+        uuid: Uuid,
+        source: Source,
+        anomaly: SessionAnomaly,
+        time: OffsetDateTime,
+    },
+    /// The credentials of this account were changed.
+    CredentialUpdated { uuid: Uuid, time: OffsetDateTime },
+    /// A new passkey was enrolled to this account.
+    PasskeyEnrolled {
+        uuid: Uuid,
+        label: String,
+        time: OffsetDateTime,
+    },
+    /// A credential of this account was locked due to repeated authentication failures.
+    AccountLocked {
+        uuid: Uuid,
+        until: OffsetDateTime,
+        time: OffsetDateTime,
+    },
+    /// An account requested to join a group that this account is able to approve requests for.
+    GroupMembershipRequested {
+        uuid: Uuid,
+        /// The spn of the account that made the request.
+        requester: String,
+        /// The spn of the group that was requested.
+        group: String,
+        justification: Option<String>,
+        time: OffsetDateTime,
+    },
+}
+
+impl SecurityNotification {
+    pub fn uuid(&self) -> Uuid {
+        match self {
+            SecurityNotification::SessionCreated { uuid, .. }
+            | SecurityNotification::UnusualSignIn { uuid, .. }
+            | SecurityNotification::CredentialUpdated { uuid, .. }
+            | SecurityNotification::PasskeyEnrolled { uuid, .. }
+            | SecurityNotification::AccountLocked { uuid, .. }
+            | SecurityNotification::GroupMembershipRequested { uuid, .. } => *uuid,
+        }
+    }
+}
+
+/// Where a notification about an account should be delivered to.
+#[derive(Debug, Clone)]
+pub struct NotificationRecipient {
+    pub uuid: Uuid,
+    pub spn: String,
+    pub displayname: String,
+    pub mail: String,
+    /// The categories of notification that the owner has opted out of. Whether an opt out is
+    /// honoured is decided by the server configuration.
+    pub opt_out: BTreeSet<String>,
+}
+
+impl NotificationRecipient {
+    pub fn has_opted_out(&self, category: &str) -> bool {
+        self.opt_out.contains(category)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpiringApiToken {
+    pub recipient: NotificationRecipient,
+    pub label: String,
+    pub expiry: OffsetDateTime,
+}
+
+/// An ssh public key that is about to expire, after which it is no longer distributed to
+/// clients.
+#[derive(Debug, Clone)]
+pub struct ExpiringSshKey {
+    pub recipient: NotificationRecipient,
+    pub label: String,
+    pub expiry: OffsetDateTime,
+}
+
+/// A contractor account that is about to lapse, and the sponsor who can renew it.
+#[derive(Debug, Clone)]
+pub struct ExpiringContractor {
+    pub sponsor: NotificationRecipient,
+    pub spn: String,
+    pub displayname: String,
+    pub expiry: OffsetDateTime,
+}
+
+pub(crate) type NotificationSender = Option<Sender<SecurityNotification>>;
+
+pub(crate) fn send_notification(
+    notify_tx: &NotificationSender,
+    notification: SecurityNotification,
+) {
+    if let Some(notify_tx) = notify_tx {
+        if notify_tx.send(notification).is_err() {
+            error!("Unable to submit security notification to queue");
+        }
+    }
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    fn notification_recipient_from_entry(
+        entry: &EntrySealedCommitted,
+    ) -> Option<NotificationRecipient> {
+        let mail = entry.get_ava_mail_primary(Attribute::Mail)?.to_string();
+        let spn = entry.get_ava_single_proto_string(Attribute::Spn)?;
+        let displayname = entry
+            .get_ava_single_utf8(Attribute::DisplayName)
+            .map(str::to_string)
+            .unwrap_or_else(|| spn.clone());
+        let opt_out = entry
+            .get_ava_iter_iutf8(Attribute::NotificationOptOut)
+            .map(|i| i.map(str::to_string).collect())
+            .unwrap_or_default();
+
+        Some(NotificationRecipient {
+            uuid: entry.get_uuid(),
+            spn,
+            displayname,
+            mail,
+            opt_out,
+        })
+    }
+
+    /// Find where notifications for this account should be sent. Accounts without a mail
+    /// address don't receive notifications.
+    pub fn notification_recipient(
+        &mut self,
+        uuid: Uuid,
+    ) -> Result<Option<NotificationRecipient>, OperationError> {
+        match self.qs_read.internal_search_uuid(uuid) {
+            Ok(entry) => Ok(Self::notification_recipient_from_entry(entry.as_ref())),
+            Err(OperationError::NoMatchingEntries) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Find api tokens that expire within the window between `from` and `to`, for accounts
+    /// that are able to be notified.
+    pub fn expiring_api_tokens(
+        &mut self,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> Result<Vec<ExpiringApiToken>, OperationError> {
+        let filter = filter!(f_and!([
+            f_pres(Attribute::ApiTokenSession),
+            f_pres(Attribute::Mail)
+        ]));
+
+        let entries = self.qs_read.internal_search(filter)?;
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| {
+                let recipient = Self::notification_recipient_from_entry(entry.as_ref())?;
+                let tokens = entry.get_ava_as_apitoken_map(Attribute::ApiTokenSession)?;
+                Some(
+                    tokens
+                        .values()
+                        .filter_map(|token| {
+                            let expiry = token.expiry?;
+                            (from <= expiry && expiry < to).then(|| ExpiringApiToken {
+                                recipient: recipient.clone(),
+                                label: token.label.clone(),
+                                expiry,
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .flatten()
+            .collect())
+    }
+
+    /// Find ssh public keys that expire within the window between `from` and `to`, for
+    /// accounts that are able to be notified.
+    pub fn expiring_ssh_keys(
+        &mut self,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> Result<Vec<ExpiringSshKey>, OperationError> {
+        let filter = filter!(f_and!([
+            f_pres(Attribute::SshPublicKeyExpiry),
+            f_pres(Attribute::Mail)
+        ]));
+
+        let entries = self.qs_read.internal_search(filter)?;
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| {
+                let recipient = Self::notification_recipient_from_entry(entry.as_ref())?;
+                let expiries = entry
+                    .get_ava_set(Attribute::SshPublicKeyExpiry)
+                    .and_then(|vs| vs.as_utf8_iter())?;
+                Some(
+                    expiries
+                        .filter_map(|expiry| SshPublicKeyExpiry::from_str(expiry).ok())
+                        .filter(|expiry| from <= expiry.expiry && expiry.expiry < to)
+                        .map(|SshPublicKeyExpiry { label, expiry }| ExpiringSshKey {
+                            recipient: recipient.clone(),
+                            label,
+                            expiry,
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .flatten()
+            .collect())
+    }
+
+    /// Find contractors whose accounts expire within the window between `from` and `to`,
+    /// where their sponsor is able to be notified.
+    pub fn expiring_contractors(
+        &mut self,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> Result<Vec<ExpiringContractor>, OperationError> {
+        let filter = filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Contractor.into()),
+            f_pres(Attribute::Sponsor)
+        ]));
+
+        let entries = self.qs_read.internal_search(filter)?;
+
+        let mut contractors = Vec::with_capacity(entries.len());
+
+        for entry in entries.iter() {
+            let Some(expiry) = entry.get_ava_single_datetime(Attribute::AccountExpire) else {
+                continue;
+            };
+
+            if expiry < from || to <= expiry {
+                continue;
+            }
+
+            let Some(sponsor) = entry.get_ava_single_refer(Attribute::Sponsor) else {
+                continue;
+            };
+
+            let Some(sponsor) = self.notification_recipient(sponsor)? else {
+                debug!(uuid = ?entry.get_uuid(), "Contractor sponsor has no mail address, not notifying");
+                continue;
+            };
+
+            let spn = entry
+                .get_ava_single_proto_string(Attribute::Spn)
+                .unwrap_or_else(|| entry.get_uuid().to_string());
+            let displayname = entry
+                .get_ava_single_utf8(Attribute::DisplayName)
+                .map(str::to_string)
+                .unwrap_or_else(|| spn.clone());
+
+            contractors.push(ExpiringContractor {
+                sponsor,
+                spn,
+                displayname,
+                expiry,
+            });
+        }
+
+        Ok(contractors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    const SPONSOR_UUID: Uuid = uuid!("5c4a3e6e-7f2b-4d1c-9a55-0f1f9f3d8b21");
+    const CONTRACTOR_UUID: Uuid = uuid!("0a7b0c5d-2b7e-4f8a-8d2f-6b3c1e9a4f10");
+
+    #[idm_test]
+    async fn test_idm_notification_expiring_contractors(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let expiry = Duration::from_secs(TEST_CURRENT_TIME + 86400 * 7);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let sponsor = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("sponsor")),
+            (Attribute::Uuid, Value::Uuid(SPONSOR_UUID)),
+            (Attribute::DisplayName, Value::new_utf8s("Sponsor")),
+            (
+                Attribute::Mail,
+                Value::EmailAddress("sponsor@example.com".to_string(), true)
+            )
+        );
+
+        let contractor = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Contractor.to_value()),
+            (Attribute::Name, Value::new_iname("contractor")),
+            (Attribute::Uuid, Value::Uuid(CONTRACTOR_UUID)),
+            (Attribute::DisplayName, Value::new_utf8s("Contractor")),
+            (Attribute::Sponsor, Value::Refer(SPONSOR_UUID)),
+            (Attribute::AccountExpire, Value::new_datetime_epoch(expiry))
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![sponsor, contractor])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let expiry = OffsetDateTime::UNIX_EPOCH + expiry;
+        let day = time::Duration::days(1);
+
+        let contractors = idms_prox_read
+            .expiring_contractors(expiry - day, expiry + day)
+            .expect("Failed to find expiring contractors");
+        assert_eq!(contractors.len(), 1);
+        assert_eq!(contractors[0].sponsor.uuid, SPONSOR_UUID);
+        assert_eq!(contractors[0].sponsor.mail, "sponsor@example.com");
+        assert_eq!(contractors[0].displayname, "Contractor");
+        assert_eq!(contractors[0].expiry, expiry);
+
+        // Outside of the window, nothing is found.
+        let contractors = idms_prox_read
+            .expiring_contractors(expiry + day, expiry + day + day)
+            .expect("Failed to find expiring contractors");
+        assert!(contractors.is_empty());
+    }
+
+    #[idm_test]
+    async fn test_idm_notification_expiring_ssh_keys(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let expiry =
+            OffsetDateTime::UNIX_EPOCH + Duration::from_secs(TEST_CURRENT_TIME + 86400 * 7);
+
+        let person_uuid = Uuid::new_v4();
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let person = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(person_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("Test Person")),
+            (
+                Attribute::Mail,
+                Value::EmailAddress("testperson@example.com".to_string(), true)
+            ),
+            (
+                Attribute::SshPublicKey,
+                Value::new_sshkey_str(
+                    "laptop",
+                    "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAeGW1P6Pc2rPq0XqbRaDKBcXZUPRklo0L1EyR30CwoP"
+                )
+                .expect("Invalid ssh key")
+            ),
+            (
+                Attribute::SshPublicKeyExpiry,
+                Value::new_utf8(format!("laptop={}", expiry.format(&Rfc3339).unwrap()))
+            )
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![person])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let day = time::Duration::days(1);
+
+        let keys = idms_prox_read
+            .expiring_ssh_keys(expiry - day, expiry + day)
+            .expect("Failed to find expiring ssh keys");
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].recipient.uuid, person_uuid);
+        assert_eq!(keys[0].label, "laptop");
+        assert_eq!(keys[0].expiry, expiry);
+
+        let keys = idms_prox_read
+            .expiring_ssh_keys(expiry + day, expiry + day + day)
+            .expect("Failed to find expiring ssh keys");
+        assert!(keys.is_empty());
+    }
+}
@@ -17,27 +17,37 @@ use hashbrown::HashSet;
 
 pub use compact_jwt::{compact::JwkKeySet, OidcToken};
 use compact_jwt::{
-    crypto::JwsRs256Signer, jws::JwsBuilder, JwsCompact, JwsEs256Signer, JwsSigner,
+    crypto::JwsRs256Signer, jws::JwsBuilder, Jwk, Jws, JwsCompact, JwsEs256Signer, JwsSigner,
     JwsSignerToVerifier, JwsVerifier, OidcClaims, OidcSubject,
 };
 use concread::cowcell::*;
 use fernet::Fernet;
 use hashbrown::HashMap;
+use kanidm_lib_crypto::x509_cert::der::Encode;
 use kanidm_proto::constants::*;
+use kanidm_proto::internal::{
+    Oauth2ClaimTemplatePart, Oauth2ClaimTransform, Oauth2ComputedClaim, Oauth2ComputedClaimSource,
+    Oauth2GrantType, Oauth2JwtSignAlg, Oauth2RedirectUriMode, Oauth2ResponseType,
+    Oauth2ScopeDescription,
+};
 
 // #[cfg(feature = "dev-oauth2-device-flow")]
 // use kanidm_proto::oauth2::OAUTH2_DEVICE_CODE_EXPIRY_SECONDS;
 
 pub use kanidm_proto::oauth2::{
     AccessTokenIntrospectRequest, AccessTokenIntrospectResponse, AccessTokenRequest,
-    AccessTokenResponse, AuthorisationRequest, CodeChallengeMethod, ErrorResponse, GrantTypeReq,
-    OAuth2RFC9068Token, OAuth2RFC9068TokenExtensions, Oauth2Rfc8414MetadataResponse,
-    OidcDiscoveryResponse, OidcWebfingerRel, OidcWebfingerResponse, PkceAlg, TokenRevokeRequest,
+    AccessTokenResponse, AuthorisationRequest, ClientRegistrationRequest,
+    ClientRegistrationResponse, CodeChallengeMethod, ErrorResponse, GrantTypeReq,
+    OAuth2RFC9068Token, OAuth2RFC9068TokenExtensions, OAuth2TokenActor, OAuth2TokenConfirmation,
+    Oauth2Rfc8414MetadataResponse, OidcDiscoveryResponse, OidcWebfingerRel, OidcWebfingerResponse,
+    PkceAlg, TokenRevokeRequest, OAUTH2_TOKEN_TYPE_ACCESS_TOKEN,
 };
 
+use kanidm_proto::v1::{Oauth2SessionStatus, UatStatusState};
+
 use kanidm_proto::oauth2::{
-    AccessTokenType, ClaimType, DeviceAuthorizationResponse, DisplayValue, GrantType,
-    IdTokenSignAlg, ResponseMode, ResponseType, SubjectType, TokenEndpointAuthMethod,
+    AccessTokenType, ApplicationType, ClaimType, DeviceAuthorizationResponse, DisplayValue,
+    GrantType, IdTokenSignAlg, ResponseMode, ResponseType, SubjectType, TokenEndpointAuthMethod,
 };
 use openssl::sha;
 
@@ -49,11 +59,16 @@ use uri::{OAUTH2_TOKEN_INTROSPECT_ENDPOINT, OAUTH2_TOKEN_REVOKE_ENDPOINT};
 use url::{Host, Origin, Url};
 
 use crate::idm::account::Account;
+use crate::idm::audit::AuditEvent;
+use crate::idm::dpop::DPoPProof;
 use crate::idm::server::{
     IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction, IdmServerTransaction,
 };
 use crate::prelude::*;
-use crate::value::{Oauth2Session, OauthClaimMapJoin, SessionState, OAUTHSCOPE_RE};
+use crate::server::keys::{KeyObject, KeyProvidersTransaction};
+use crate::value::{
+    Oauth2Session, Oauth2SessionRevokeReason, OauthClaimMapJoin, SessionState, OAUTHSCOPE_RE,
+};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -62,6 +77,8 @@ pub enum Oauth2Error {
     AuthenticationRequired,
     InvalidClientId,
     InvalidOrigin,
+    // The account is not assigned to this client. Reported as access_denied.
+    ClientNotAssigned,
     // Standard
     InvalidRequest,
     InvalidGrant,
@@ -95,6 +112,18 @@ pub enum Oauth2Error {
     ///   authorization request but SHOULD wait for user interaction before
     ///   restarting to avoid unnecessary polling.
     ExpiredToken,
+    // from https://datatracker.ietf.org/doc/html/rfc7591#section-3.2.2
+    InvalidRedirectUri,
+    InvalidClientMetadata,
+    // from https://datatracker.ietf.org/doc/html/rfc8693#section-2.2.2
+    InvalidTarget,
+    // from https://datatracker.ietf.org/doc/html/rfc9449#section-12.2
+    InvalidDpopProof,
+    // The redirect uri of an authorisation request was rejected, and why.
+    RedirectUriRejected(OperationError),
+    // from https://openid.net/specs/openid-connect-core-1_0.html#AuthError
+    LoginRequired,
+    ConsentRequired,
 }
 
 impl std::fmt::Display for Oauth2Error {
@@ -103,6 +132,7 @@ impl std::fmt::Display for Oauth2Error {
             Oauth2Error::AuthenticationRequired => "authentication_required",
             Oauth2Error::InvalidClientId => "invalid_client_id",
             Oauth2Error::InvalidOrigin => "invalid_origin",
+            Oauth2Error::ClientNotAssigned => "access_denied",
             Oauth2Error::InvalidGrant => "invalid_grant",
             Oauth2Error::InvalidRequest => "invalid_request",
             Oauth2Error::UnauthorizedClient => "unauthorized_client",
@@ -117,12 +147,20 @@ impl std::fmt::Display for Oauth2Error {
             Oauth2Error::SlowDown => "slow_down",
             Oauth2Error::AuthorizationPending => "authorization_pending",
             Oauth2Error::ExpiredToken => "expired_token",
+            Oauth2Error::InvalidRedirectUri => "invalid_redirect_uri",
+            Oauth2Error::InvalidClientMetadata => "invalid_client_metadata",
+            Oauth2Error::InvalidTarget => "invalid_target",
+            Oauth2Error::InvalidDpopProof => "invalid_dpop_proof",
+            // This is the error that clients expect for a redirect uri that isn't registered.
+            Oauth2Error::RedirectUriRejected(_) => "invalid_origin",
+            Oauth2Error::LoginRequired => "login_required",
+            Oauth2Error::ConsentRequired => "consent_required",
         })
     }
 }
 
 // == internal state formats that we encrypt and send.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
 enum SupportedResponseMode {
     Query,
     Fragment,
@@ -188,6 +226,13 @@ pub(crate) enum Oauth2TokenType {
         nbf: i64,
         // We stash some details here for oidc.
         nonce: Option<String>,
+        // The client that obtained this session through a token exchange.
+        #[serde(default)]
+        actor: Option<String>,
+        // The DPoP key this token is bound to. Refresh tokens are not bound to a client
+        // certificate, so that the client can renew its certificate.
+        #[serde(default)]
+        cnf: Option<OAuth2TokenConfirmation>,
     },
     ClientAccess {
         scopes: BTreeSet<String>,
@@ -196,6 +241,8 @@ pub(crate) enum Oauth2TokenType {
         exp: i64,
         iat: i64,
         nbf: i64,
+        #[serde(default)]
+        cnf: Option<OAuth2TokenConfirmation>,
     },
 }
 
@@ -227,12 +274,18 @@ pub enum AuthoriseResponse {
         scopes: BTreeSet<String>,
         // Extra PII that may be requested
         pii_scopes: BTreeSet<String>,
+        // Descriptions of the requested scopes, in any language they are available in.
+        scope_descriptions: Vec<Oauth2ScopeDescription>,
+        // The names of the custom claims that will be released about the user.
+        claims: BTreeSet<String>,
         // The users displayname (?)
         // pub display_name: String,
         // The token we need to be given back to allow this to proceed
         consent_token: String,
     },
     Permitted(AuthorisePermitSuccess),
+    // The client asked that the user not be prompted, but that is needed to proceed.
+    Rejected(AuthoriseReject),
 }
 
 #[derive(Debug)]
@@ -285,12 +338,51 @@ impl AuthorisePermitSuccess {
     }
 }
 
+/// The prompt parameter of an OIDC authorisation request.
+/// <https://openid.net/specs/openid-connect-core-1_0.html#AuthRequest>
+#[derive(Debug, Default)]
+struct OidcPrompt {
+    /// The user must not be shown any authentication or consent pages.
+    none: bool,
+    /// The user must authenticate, even if they have a valid session.
+    login: bool,
+    /// The user must consent, even if they consented previously.
+    consent: bool,
+}
+
+impl OidcPrompt {
+    fn parse(prompt: Option<&str>) -> Result<Self, Oauth2Error> {
+        let mut parsed = OidcPrompt::default();
+
+        for value in prompt.unwrap_or_default().split_ascii_whitespace() {
+            match value {
+                "none" => parsed.none = true,
+                "login" => parsed.login = true,
+                "consent" => parsed.consent = true,
+                // A session only has one account, so there is nothing to select.
+                "select_account" => {}
+                _ => warn!(?value, "Ignoring unsupported prompt value"),
+            }
+        }
+
+        if parsed.none && (parsed.login || parsed.consent) {
+            warn!("prompt=none can not be combined with other prompt values");
+            return Err(Oauth2Error::InvalidRequest);
+        }
+
+        Ok(parsed)
+    }
+}
+
 #[derive(Debug)]
 pub struct AuthoriseReject {
     // Where the client wants us to go back to.
     pub redirect_uri: Url,
+    // The CSRF as a string
+    pub state: Option<String>,
     /// The format the response should be returned to the application in.
     response_mode: SupportedResponseMode,
+    pub error: Oauth2Error,
 }
 
 impl AuthoriseReject {
@@ -303,11 +395,21 @@ impl AuthoriseReject {
         redirect_uri.set_query(None);
         redirect_uri.set_fragment(None);
 
+        let description = match self.error {
+            Oauth2Error::LoginRequired => "authentication required",
+            Oauth2Error::ConsentRequired => "consent required",
+            _ => "authorisation rejected",
+        };
+
         // We can't set query pairs on fragments, only query.
-        let encoded = url::form_urlencoded::Serializer::new(String::new())
-            .append_pair("error", "access_denied")
-            .append_pair("error_description", "authorisation rejected")
-            .finish();
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        serializer
+            .append_pair("error", &self.error.to_string())
+            .append_pair("error_description", description);
+        if let Some(state) = self.state.as_ref() {
+            serializer.append_pair("state", state);
+        }
+        let encoded = serializer.finish();
 
         match self.response_mode {
             SupportedResponseMode::Query => redirect_uri.set_query(Some(&encoded)),
@@ -318,15 +420,50 @@ impl AuthoriseReject {
     }
 }
 
+#[derive(Clone)]
+struct Oauth2ClientSecret {
+    secret: String,
+    expiry: Option<OffsetDateTime>,
+    last_used: Option<OffsetDateTime>,
+}
+
+impl Oauth2ClientSecret {
+    fn is_valid(&self, secret: &str, odt_ct: OffsetDateTime) -> bool {
+        self.secret == secret && self.expiry.map(|expiry| odt_ct < expiry).unwrap_or(true)
+    }
+}
+
+/// Which of the secrets of a confidential client was presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientSecretKind {
+    Current,
+    Previous,
+}
+
+impl ClientSecretKind {
+    fn last_used_attr(self) -> Attribute {
+        match self {
+            ClientSecretKind::Current => Attribute::OAuth2RsBasicSecretLastUsed,
+            ClientSecretKind::Previous => Attribute::OAuth2RsBasicSecretPreviousLastUsed,
+        }
+    }
+}
+
 #[derive(Clone)]
 enum OauthRSType {
     Basic {
-        authz_secret: String,
+        authz_secret: Oauth2ClientSecret,
+        // After a rotation the replaced secret remains valid until it expires, so that
+        // clients can be updated without an outage.
+        previous_authz_secret: Option<Oauth2ClientSecret>,
         enable_pkce: bool,
     },
     // Public clients must have pkce.
     Public {
         allow_localhost_redirect: bool,
+        // Native applications may use private-use scheme and loopback redirects on any port, and
+        // are issued shorter lived tokens.
+        native: bool,
     },
 }
 
@@ -337,9 +474,80 @@ impl OauthRSType {
             OauthRSType::Basic { .. } => false,
             OauthRSType::Public {
                 allow_localhost_redirect,
+                ..
             } => *allow_localhost_redirect,
         }
     }
+
+    fn is_native(&self) -> bool {
+        match self {
+            OauthRSType::Basic { .. } => false,
+            OauthRSType::Public { native, .. } => *native,
+        }
+    }
+
+    /// Check the secret presented by a confidential client, returning which secret it
+    /// matched and when that secret was last used.
+    fn check_basic_secret(
+        &self,
+        secret: &str,
+        ct: Duration,
+    ) -> Option<(ClientSecretKind, Option<OffsetDateTime>)> {
+        let OauthRSType::Basic {
+            authz_secret,
+            previous_authz_secret,
+            ..
+        } = self
+        else {
+            return None;
+        };
+
+        let odt_ct = OffsetDateTime::UNIX_EPOCH + ct;
+
+        if authz_secret.is_valid(secret, odt_ct) {
+            Some((ClientSecretKind::Current, authz_secret.last_used))
+        } else if let Some(previous) = previous_authz_secret
+            .as_ref()
+            .filter(|previous| previous.is_valid(secret, odt_ct))
+        {
+            security_info!(
+                expiry = ?previous.expiry,
+                "OAuth2 client authenticated with its previous secret, it must be updated before this expires"
+            );
+            Some((ClientSecretKind::Previous, previous.last_used))
+        } else {
+            None
+        }
+    }
+}
+
+/// Record that a client secret was used, at most once per
+/// [`OAUTH2_SECRET_LAST_USED_GRANULARITY`] so that token requests don't each cause a write.
+fn record_client_secret_use(
+    qs_write: &mut QueryServerWriteTransaction,
+    client_uuid: Uuid,
+    (kind, last_used): (ClientSecretKind, Option<OffsetDateTime>),
+    ct: Duration,
+) -> Result<(), Oauth2Error> {
+    let odt_ct = OffsetDateTime::UNIX_EPOCH + ct;
+    let granularity = time::Duration::seconds(OAUTH2_SECRET_LAST_USED_GRANULARITY as i64);
+
+    if last_used
+        .map(|last_used| odt_ct - last_used < granularity)
+        .unwrap_or(false)
+    {
+        return Ok(());
+    }
+
+    qs_write
+        .internal_modify_uuid(
+            client_uuid,
+            &ModifyList::new_purge_and_set(kind.last_used_attr(), Value::new_datetime(odt_ct)),
+        )
+        .map_err(|e| {
+            admin_error!("Failed to record OAuth2 client secret use {:?}", e);
+            Oauth2Error::ServerError(e)
+        })
 }
 
 impl std::fmt::Debug for OauthRSType {
@@ -351,9 +559,11 @@ impl std::fmt::Debug for OauthRSType {
             }
             OauthRSType::Public {
                 allow_localhost_redirect,
+                native,
             } => ds
                 .field("type", &"public")
-                .field("allow_localhost_redirect", allow_localhost_redirect),
+                .field("allow_localhost_redirect", allow_localhost_redirect)
+                .field("native", native),
         };
         ds.finish()
     }
@@ -363,6 +573,9 @@ impl std::fmt::Debug for OauthRSType {
 enum Oauth2JwsSigner {
     ES256 { signer: JwsEs256Signer },
     RS256 { signer: JwsRs256Signer },
+    // The signing keys are held in the key object of the client, which shares the uuid
+    // of the client.
+    KeyObject { sign_alg: Oauth2JwtSignAlg },
 }
 
 #[derive(Clone, Debug)]
@@ -409,12 +622,24 @@ pub struct Oauth2RS {
     redirect_uris: HashSet<Url>,
     origin_https_required: bool,
     strict_redirect_uri: bool,
+    redirect_uri_mode: Oauth2RedirectUriMode,
+    // If set, the only grant types and response types that this client may use.
+    allowed_grant_types: Option<BTreeSet<Oauth2GrantType>>,
+    allowed_response_types: Option<BTreeSet<Oauth2ResponseType>>,
 
     claim_map: BTreeMap<Uuid, Vec<(String, ClaimValue)>>,
+    computed_claims: Vec<Oauth2ComputedClaim>,
     scope_maps: BTreeMap<Uuid, BTreeSet<String>>,
     sup_scope_maps: BTreeMap<Uuid, BTreeSet<String>>,
+    scope_descriptions: Vec<Oauth2ScopeDescription>,
     client_scopes: BTreeSet<String>,
     client_sup_scopes: BTreeSet<String>,
+    // The clients that this client may exchange a user's access token for.
+    token_exchange_audiences: BTreeSet<Uuid>,
+    // If not empty, only these accounts and members of these groups may use this client.
+    assigned_members: BTreeSet<Uuid>,
+    // If each refresh must issue a new refresh token, revoking the session on reuse.
+    refresh_token_rotation: bool,
     // Our internal exchange encryption material for this rs.
     token_fernet: Fernet,
     jws_signer: Oauth2JwsSigner,
@@ -438,6 +663,101 @@ pub struct Oauth2RS {
 }
 
 impl Oauth2RS {
+    fn id_token_sign_alg(&self) -> IdTokenSignAlg {
+        match &self.jws_signer {
+            Oauth2JwsSigner::ES256 { .. }
+            | Oauth2JwsSigner::KeyObject {
+                sign_alg: Oauth2JwtSignAlg::Es256,
+            } => IdTokenSignAlg::ES256,
+            Oauth2JwsSigner::RS256 { .. }
+            | Oauth2JwsSigner::KeyObject {
+                sign_alg: Oauth2JwtSignAlg::Rs256,
+            } => IdTokenSignAlg::RS256,
+        }
+    }
+
+    fn key_object<K: KeyProvidersTransaction>(
+        &self,
+        key_providers: &K,
+    ) -> Result<Arc<KeyObject>, OperationError> {
+        key_providers
+            .get_key_object_handle(self.uuid)
+            .ok_or_else(|| {
+                error!(client_id = %self.name, "OAuth2 client key object is not available");
+                OperationError::KP0070KeyObjectNoJwsSigningKey
+            })
+    }
+
+    fn jws_sign<K: KeyProvidersTransaction>(
+        &self,
+        key_providers: &K,
+        jws: &Jws,
+        ct: Duration,
+    ) -> Result<JwsCompact, OperationError> {
+        match &self.jws_signer {
+            Oauth2JwsSigner::ES256 { signer } => signer.sign(jws).map_err(|err| {
+                error!(?err, "Unable to sign jws");
+                OperationError::CryptographyError
+            }),
+            Oauth2JwsSigner::RS256 { signer } => signer.sign(jws).map_err(|err| {
+                error!(?err, "Unable to sign jws");
+                OperationError::CryptographyError
+            }),
+            Oauth2JwsSigner::KeyObject { sign_alg } => {
+                let key_object = self.key_object(key_providers)?;
+                match sign_alg {
+                    Oauth2JwtSignAlg::Es256 => key_object.jws_es256_sign(jws, ct),
+                    Oauth2JwtSignAlg::Rs256 => key_object.jws_rs256_sign(jws, ct),
+                }
+            }
+        }
+    }
+
+    fn jws_verify<K: KeyProvidersTransaction>(
+        &self,
+        key_providers: &K,
+        jwsc: &JwsCompact,
+    ) -> Result<Jws, OperationError> {
+        match &self.jws_signer {
+            Oauth2JwsSigner::ES256 { signer } => signer
+                .get_verifier()
+                .and_then(|verifier| verifier.verify(jwsc))
+                .map_err(|err| {
+                    error!(?err, "Unable to verify jws");
+                    OperationError::CryptographyError
+                }),
+            Oauth2JwsSigner::RS256 { signer } => signer
+                .get_verifier()
+                .and_then(|verifier| verifier.verify(jwsc))
+                .map_err(|err| {
+                    error!(?err, "Unable to verify jws");
+                    OperationError::CryptographyError
+                }),
+            Oauth2JwsSigner::KeyObject { .. } => self.key_object(key_providers)?.jws_verify(jwsc),
+        }
+    }
+
+    fn public_jwks<K: KeyProvidersTransaction>(
+        &self,
+        key_providers: &K,
+    ) -> Result<Vec<Jwk>, OperationError> {
+        let jwk = match &self.jws_signer {
+            Oauth2JwsSigner::ES256 { signer } => signer.public_key_as_jwk(),
+            Oauth2JwsSigner::RS256 { signer } => signer.public_key_as_jwk(),
+            // Includes the retained keys, so that tokens signed before a rotation can
+            // still be verified.
+            Oauth2JwsSigner::KeyObject { .. } => {
+                return self.key_object(key_providers)?.jws_public_jwks()
+            }
+        }
+        .map_err(|err| {
+            error!(?err, client_id = %self.name, "Unable to retrieve public key");
+            OperationError::InvalidState
+        })?;
+
+        Ok(vec![jwk])
+    }
+
     pub fn is_basic(&self) -> bool {
         match self.type_ {
             OauthRSType::Basic { .. } => true,
@@ -460,10 +780,196 @@ impl Oauth2RS {
         }
     }
 
+    /// Is this a public client for a native application?
+    pub fn is_native(&self) -> bool {
+        self.type_.is_native()
+    }
+
+    /// Is this identity assigned to this client? A client with no assigned members is
+    /// available to anyone that holds a scope map.
+    pub fn is_assigned(&self, ident: &Identity) -> bool {
+        self.assigned_members.is_empty()
+            || ident
+                .get_uuid()
+                .map(|u| self.assigned_members.contains(&u))
+                .unwrap_or(false)
+            || ident
+                .get_memberof()
+                .map(|mo| !mo.is_disjoint(&self.assigned_members))
+                .unwrap_or(false)
+    }
+
     /// Does this RS have device flow enabled?
     pub fn device_flow_enabled(&self) -> bool {
         self.device_authorization_endpoint.is_some()
     }
+
+    /// How long, in seconds, access tokens issued to this client are valid for.
+    fn access_token_expiry(&self) -> u32 {
+        if self.is_native() {
+            OAUTH2_NATIVE_ACCESS_TOKEN_EXPIRY
+        } else {
+            OAUTH2_ACCESS_TOKEN_EXPIRY
+        }
+    }
+
+    /// How long, in seconds, refresh tokens issued to this client are valid for.
+    fn refresh_token_expiry(&self) -> u64 {
+        if self.is_native() {
+            OAUTH2_NATIVE_REFRESH_TOKEN_EXPIRY
+        } else {
+            OAUTH_REFRESH_TOKEN_EXPIRY
+        }
+    }
+
+    /// Is this client permitted to use this grant type at the token endpoint?
+    fn grant_type_allowed(&self, grant_type: Oauth2GrantType) -> bool {
+        self.allowed_grant_types
+            .as_ref()
+            .map(|allowed| allowed.contains(&grant_type))
+            .unwrap_or(true)
+    }
+
+    /// Is this client permitted to request this response type at the authorisation endpoint?
+    fn response_type_allowed(&self, response_type: &ResponseType) -> bool {
+        let response_type = match response_type {
+            ResponseType::Code => Oauth2ResponseType::Code,
+            ResponseType::Token => Oauth2ResponseType::Token,
+            ResponseType::IdToken => Oauth2ResponseType::IdToken,
+        };
+        self.allowed_response_types
+            .as_ref()
+            .map(|allowed| allowed.contains(&response_type))
+            .unwrap_or(true)
+    }
+
+    /// Native applications may always redirect to a registered loopback uri on any port.
+    fn loopback_any_port(&self) -> bool {
+        self.is_native() || self.redirect_uri_mode == Oauth2RedirectUriMode::LoopbackAnyPort
+    }
+
+    /// Native applications may always redirect to a registered private-use scheme.
+    fn private_use_scheme(&self) -> bool {
+        self.is_native() || self.redirect_uri_mode == Oauth2RedirectUriMode::PrivateUseScheme
+    }
+
+    /// Check that the redirect uri of an authorisation request is permitted for this client,
+    /// returning the reason that it was rejected if not.
+    fn check_redirect_uri(&self, redirect_uri: &Url) -> Result<(), OperationError> {
+        // redirect_uri must be part of the client_id origins, unless the client is public and then it MAY
+        // be a loopback address exempting it from this check and enforcement and we can carry on safely.
+        if self.type_.allow_localhost_redirect() && check_is_loopback(redirect_uri) {
+            debug!("Loopback redirect_uri detected, allowing for localhost");
+            return Ok(());
+        }
+
+        if self.loopback_any_port() && check_is_loopback(redirect_uri) {
+            return self.check_loopback_any_port(redirect_uri);
+        }
+
+        // Allow opaque origins such as app uris. Non-http origins are exempt from the https
+        // enforcement below.
+        if self.opaque_origins.contains(redirect_uri) {
+            return Ok(());
+        }
+
+        let matched = if self.strict_redirect_uri {
+            // Strict uri validation is in use.
+            self.redirect_uris.contains(redirect_uri)
+        } else {
+            // The legacy origin match is in use.
+            self.origins.contains(&redirect_uri.origin())
+        };
+
+        if !matched {
+            if self.private_use_scheme() && !matches!(redirect_uri.scheme(), "http" | "https") {
+                return self.check_private_use_scheme(redirect_uri);
+            }
+
+            return if self.strict_redirect_uri {
+                warn!(
+                    "Invalid OAuth2 redirect_uri (must be an exact match to a redirect-url) - got {}",
+                    redirect_uri.as_str()
+                );
+                Err(OperationError::OA0001RedirectUriNotRegistered)
+            } else {
+                warn!(
+                    "Invalid OAuth2 redirect_uri (must be related to origin) - got {:?}",
+                    redirect_uri.origin()
+                );
+                Err(OperationError::OA0002RedirectUriOriginNotRegistered)
+            };
+        }
+
+        // We have to specifically match on http here because non-http origins may be exempt from this
+        // enforcement.
+        if self.origin_https_required && redirect_uri.scheme() != "https" {
+            admin_warn!(
+                "Invalid OAuth2 redirect_uri scheme (must be https for secure origin) - got {}",
+                redirect_uri.to_string()
+            );
+            return Err(OperationError::OA0003RedirectUriHttpsRequired);
+        }
+
+        Ok(())
+    }
+
+    /// Native applications listen on an ephemeral port of the loopback interface, so the port
+    /// is ignored when comparing to the registered loopback redirect uris. Plain http is
+    /// expected here, as the request never leaves the device.
+    /// ref <https://www.rfc-editor.org/rfc/rfc8252#section-7.3>
+    fn check_loopback_any_port(&self, redirect_uri: &Url) -> Result<(), OperationError> {
+        let without_port = |uri: &Url| {
+            let mut uri = uri.clone();
+            // This can only fail for uris that can't have a port, which are never loopback.
+            let _ = uri.set_port(None);
+            uri
+        };
+
+        let candidate = without_port(redirect_uri);
+
+        if self
+            .redirect_uris
+            .iter()
+            .filter(|uri| check_is_loopback(uri))
+            .any(|uri| without_port(uri) == candidate)
+        {
+            debug!("Loopback redirect_uri matched a registered redirect uri on another port");
+            Ok(())
+        } else {
+            warn!(
+                "Invalid OAuth2 redirect_uri (must match a registered loopback redirect uri, other than the port) - got {}",
+                redirect_uri.as_str()
+            );
+            Err(OperationError::OA0004RedirectUriLoopbackNotRegistered)
+        }
+    }
+
+    /// Native applications may claim a private-use scheme that is the reverse of a domain name
+    /// they control, and then receive redirects to any path of that scheme.
+    /// ref <https://www.rfc-editor.org/rfc/rfc8252#section-7.1>
+    fn check_private_use_scheme(&self, redirect_uri: &Url) -> Result<(), OperationError> {
+        let scheme = redirect_uri.scheme();
+
+        if !scheme.contains('.') {
+            warn!(
+                "Invalid OAuth2 redirect_uri (private-use scheme must be a reverse domain name) - got {}",
+                scheme
+            );
+            return Err(OperationError::OA0005RedirectUriPrivateUseSchemeInvalid);
+        }
+
+        if self.opaque_origins.iter().any(|uri| uri.scheme() == scheme) {
+            debug!("Private-use scheme redirect_uri matched a registered scheme");
+            Ok(())
+        } else {
+            warn!(
+                "Invalid OAuth2 redirect_uri (private-use scheme is not registered) - got {}",
+                scheme
+            );
+            Err(OperationError::OA0006RedirectUriPrivateUseSchemeNotRegistered)
+        }
+    }
 }
 
 impl std::fmt::Debug for Oauth2RS {
@@ -475,9 +981,14 @@ impl std::fmt::Debug for Oauth2RS {
             .field("type", &self.type_)
             .field("origins", &self.origins)
             .field("opaque_origins", &self.opaque_origins)
+            .field("redirect_uri_mode", &self.redirect_uri_mode)
+            .field("allowed_grant_types", &self.allowed_grant_types)
+            .field("allowed_response_types", &self.allowed_response_types)
             .field("scope_maps", &self.scope_maps)
             .field("sup_scope_maps", &self.sup_scope_maps)
+            .field("scope_descriptions", &self.scope_descriptions)
             .field("claim_map", &self.claim_map)
+            .field("computed_claims", &self.computed_claims)
             .field("has_custom_image", &self.has_custom_image)
             .finish()
     }
@@ -540,6 +1051,13 @@ impl Oauth2ResourceServers {
     }
 }
 
+impl Oauth2ResourceServersReadTransaction {
+    /// The origin of this server, which the endpoints of other protocols are relative to.
+    pub(crate) fn origin(&self) -> &Url {
+        &self.inner.origin
+    }
+}
+
 impl Oauth2ResourceServersWriteTransaction<'_> {
     pub fn reload(
         &mut self,
@@ -561,9 +1079,21 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
                 let type_ = if ent.attribute_equality(Attribute::Class, &EntryClass::OAuth2ResourceServerBasic.into()) {
                     let authz_secret = ent
                         .get_ava_single_secret(Attribute::OAuth2RsBasicSecret)
-                        .map(str::to_string)
+                        .map(|secret| Oauth2ClientSecret {
+                            secret: secret.to_string(),
+                            expiry: ent.get_ava_single_datetime(Attribute::OAuth2RsBasicSecretExpiry),
+                            last_used: ent.get_ava_single_datetime(Attribute::OAuth2RsBasicSecretLastUsed),
+                        })
                         .ok_or(OperationError::InvalidValueState)?;
 
+                    let previous_authz_secret = ent
+                        .get_ava_single_secret(Attribute::OAuth2RsBasicSecretPrevious)
+                        .map(|secret| Oauth2ClientSecret {
+                            secret: secret.to_string(),
+                            expiry: ent.get_ava_single_datetime(Attribute::OAuth2RsBasicSecretPreviousExpiry),
+                            last_used: ent.get_ava_single_datetime(Attribute::OAuth2RsBasicSecretPreviousLastUsed),
+                        });
+
                     let enable_pkce = ent
                         .get_ava_single_bool(Attribute::OAuth2AllowInsecureClientDisablePkce)
                         .map(|e| !e)
@@ -571,6 +1101,7 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
 
                     OauthRSType::Basic {
                         authz_secret,
+                        previous_authz_secret,
                         enable_pkce,
                     }
                 } else if ent.attribute_equality(Attribute::Class, &EntryClass::OAuth2ResourceServerPublic.into()) {
@@ -578,8 +1109,11 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
                         .get_ava_single_bool(Attribute::OAuth2AllowLocalhostRedirect)
                         .unwrap_or(false);
 
+                    let native = ent.attribute_equality(Attribute::Class, &EntryClass::OAuth2ResourceServerNative.into());
+
                     OauthRSType::Public {
-                        allow_localhost_redirect
+                        allow_localhost_redirect,
+                        native,
                     }
                 } else {
                     error!("Missing class determining OAuth2 rs type");
@@ -629,6 +1163,67 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
                 // Now redirect_uris has the full set of the landing uri and the other uris
                 // that may or may not be an opaque origin. We need to split these up now.
 
+                let redirect_uri_mode = ent
+                    .get_ava_single_iutf8(Attribute::OAuth2RedirectUriMode)
+                    .and_then(|mode| {
+                        Oauth2RedirectUriMode::from_str(mode)
+                            .map_err(|_| warn!(?mode, "Ignoring invalid OAuth2 redirect uri mode"))
+                            .ok()
+                    })
+                    .unwrap_or_default();
+
+                let allowed_grant_types = ent
+                    .get_ava_iter_iutf8(Attribute::OAuth2AllowedGrantTypes)
+                    .map(|grant_types| {
+                        grant_types
+                            .filter_map(|grant_type| {
+                                Oauth2GrantType::from_str(grant_type)
+                                    .map_err(|_| warn!(?grant_type, "Ignoring invalid OAuth2 grant type"))
+                                    .ok()
+                            })
+                            .collect::<BTreeSet<_>>()
+                    });
+
+                let allowed_response_types = ent
+                    .get_ava_iter_iutf8(Attribute::OAuth2AllowedResponseTypes)
+                    .map(|response_types| {
+                        response_types
+                            .filter_map(|response_type| {
+                                Oauth2ResponseType::from_str(response_type)
+                                    .map_err(|_| warn!(?response_type, "Ignoring invalid OAuth2 response type"))
+                                    .ok()
+                            })
+                            .collect::<BTreeSet<_>>()
+                    });
+
+                let scope_descriptions = ent
+                    .get_ava_set(Attribute::OAuth2RsScopeDescription)
+                    .and_then(|vs| vs.as_utf8_iter())
+                    .map(|descriptions| {
+                        descriptions
+                            .filter_map(|description| {
+                                Oauth2ScopeDescription::from_str(description)
+                                    .map_err(|_| warn!(?description, "Ignoring invalid OAuth2 scope description"))
+                                    .ok()
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                let computed_claims = ent
+                    .get_ava_set(Attribute::OAuth2RsComputedClaim)
+                    .and_then(|vs| vs.as_utf8_iter())
+                    .map(|claims| {
+                        claims
+                            .filter_map(|claim| {
+                                Oauth2ComputedClaim::from_str(claim)
+                                    .map_err(|_| warn!(?claim, "Ignoring invalid OAuth2 computed claim"))
+                                    .ok()
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
                 let mut origins = HashSet::with_capacity(len_uris);
                 let mut redirect_uris = HashSet::with_capacity(len_uris);
                 let mut opaque_origins = HashSet::with_capacity(len_uris);
@@ -703,6 +1298,20 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
                     (BTreeSet::default(), BTreeSet::default())
                 };
 
+                let token_exchange_audiences = ent
+                    .get_ava_refer(Attribute::OAuth2TokenExchangeAudience)
+                    .cloned()
+                    .unwrap_or_default();
+
+                let assigned_members = ent
+                    .get_ava_refer(Attribute::OAuth2RsAssignedMember)
+                    .cloned()
+                    .unwrap_or_default();
+
+                let refresh_token_rotation = !ent
+                    .get_ava_single_bool(Attribute::OAuth2AllowInsecureRefreshTokenReuse)
+                    .unwrap_or(false);
+
                 let e_claim_maps = ent
                     .get_ava_set(Attribute::OAuth2RsClaimMap)
                     .and_then(|vs| vs.as_oauthclaim_map());
@@ -748,8 +1357,19 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
                     BTreeMap::default()
                 };
 
+                let sign_alg = ent
+                    .get_ava_single_iutf8(Attribute::OAuth2JwtSignAlg)
+                    .and_then(|sign_alg| {
+                        Oauth2JwtSignAlg::from_str(sign_alg)
+                            .map_err(|_| warn!(?sign_alg, "Ignoring invalid OAuth2 jwt signing algorithm"))
+                            .ok()
+                    });
+
                 trace!("{}", Attribute::OAuth2JwtLegacyCryptoEnable);
-                let jws_signer = if ent.get_ava_single_bool(Attribute::OAuth2JwtLegacyCryptoEnable).unwrap_or(false) {
+                let jws_signer = if let Some(sign_alg) = sign_alg {
+                    // Takes precedence over the legacy crypto setting.
+                    Oauth2JwsSigner::KeyObject { sign_alg }
+                } else if ent.get_ava_single_bool(Attribute::OAuth2JwtLegacyCryptoEnable).unwrap_or(false) {
                     trace!("{}", Attribute::Rs256PrivateKeyDer);
                     ent
                         .get_ava_single_private_binary(Attribute::Rs256PrivateKeyDer)
@@ -842,11 +1462,19 @@ impl Oauth2ResourceServersWriteTransaction<'_> {
                     redirect_uris,
                     origin_https_required,
                     strict_redirect_uri,
+                    redirect_uri_mode,
+                    allowed_grant_types,
+                    allowed_response_types,
                     scope_maps,
                     sup_scope_maps,
+                    scope_descriptions,
                     client_scopes,
                     client_sup_scopes,
+                    token_exchange_audiences,
+                    assigned_members,
+                    refresh_token_rotation,
                     claim_map,
+                    computed_claims,
                     token_fernet,
                     jws_signer,
                     iss,
@@ -903,11 +1531,12 @@ impl IdmServerProxyWriteTransaction<'_> {
 
         // check the secret.
         match &o2rs.type_ {
-            OauthRSType::Basic { authz_secret, .. } => {
-                if authz_secret != &secret {
+            OauthRSType::Basic { .. } => {
+                let Some(secret_use) = o2rs.type_.check_basic_secret(&secret, ct) else {
                     security_info!("Invalid OAuth2 client_id secret, this can happen if your RS is public but you configured a 'basic' type.");
                     return Err(Oauth2Error::AuthenticationRequired);
-                }
+                };
+                record_client_secret_use(&mut self.qs_write, o2rs.uuid, secret_use, ct)?;
             }
             // Relies on the token to be valid.
             OauthRSType::Public { .. } => {}
@@ -919,24 +1548,18 @@ impl IdmServerProxyWriteTransaction<'_> {
         // are either signed *or* encrypted, we need to check both options.
 
         let (session_id, expiry, uuid) = if let Ok(jwsc) = JwsCompact::from_str(&revoke_req.token) {
-            let access_token = match &o2rs.jws_signer {
-                Oauth2JwsSigner::ES256 { signer } => signer
-                    .get_verifier()
-                    .and_then(|verifier| verifier.verify(&jwsc)),
-                Oauth2JwsSigner::RS256 { signer } => signer
-                    .get_verifier()
-                    .and_then(|verifier| verifier.verify(&jwsc)),
-            }
-            .map_err(|err| {
-                admin_error!(?err, "Unable to verify access token");
-                Oauth2Error::InvalidRequest
-            })
-            .and_then(|jws| {
-                jws.from_json().map_err(|err| {
-                    admin_error!(?err, "Unable to deserialise access token");
+            let access_token = o2rs
+                .jws_verify(self.qs_write.get_key_providers(), &jwsc)
+                .map_err(|err| {
+                    admin_error!(?err, "Unable to verify access token");
                     Oauth2Error::InvalidRequest
                 })
-            })?;
+                .and_then(|jws| {
+                    jws.from_json().map_err(|err| {
+                        admin_error!(?err, "Unable to deserialise access token");
+                        Oauth2Error::InvalidRequest
+                    })
+                })?;
 
             let OAuth2RFC9068Token::<_> {
                 sub: uuid,
@@ -1038,15 +1661,15 @@ impl IdmServerProxyWriteTransaction<'_> {
 
         // check the secret.
         let client_authentication_valid = match &o2rs.type_ {
-            OauthRSType::Basic { authz_secret, .. } => {
+            OauthRSType::Basic { .. } => {
                 match secret {
                     Some(secret) => {
-                        if authz_secret == &secret {
-                            true
-                        } else {
+                        let Some(secret_use) = o2rs.type_.check_basic_secret(&secret, ct) else {
                             security_info!("Invalid OAuth2 client_id secret");
                             return Err(Oauth2Error::AuthenticationRequired);
-                        }
+                        };
+                        record_client_secret_use(&mut self.qs_write, o2rs.uuid, secret_use, ct)?;
+                        true
                     }
                     None => {
                         // We can only get here if we relied on the atr for the client_id and secret
@@ -1061,6 +1684,46 @@ impl IdmServerProxyWriteTransaction<'_> {
             OauthRSType::Public { .. } => false,
         };
 
+        // If the client proved possession of a key, the tokens we issue are bound to it.
+        let jkt = client_auth_info
+            .dpop
+            .as_ref()
+            .map(|dpop| dpop.verify(&self.oauth2rs.inner.origin, None, ct))
+            .transpose()?
+            .and_then(|cnf| cnf.jkt);
+
+        // Confidential clients that authenticated over mTLS have their access tokens bound
+        // to their certificate.
+        let x5t_s256 = if client_authentication_valid {
+            client_auth_info
+                .client_cert
+                .as_ref()
+                .map(client_cert_thumbprint)
+                .transpose()?
+        } else {
+            None
+        };
+
+        let cnf = (jkt.is_some() || x5t_s256.is_some())
+            .then_some(OAuth2TokenConfirmation { jkt, x5t_s256 });
+
+        let grant_type = match &token_req.grant_type {
+            GrantTypeReq::AuthorizationCode { .. } => Oauth2GrantType::AuthorizationCode,
+            GrantTypeReq::ClientCredentials { .. } => Oauth2GrantType::ClientCredentials,
+            GrantTypeReq::RefreshToken { .. } => Oauth2GrantType::RefreshToken,
+            GrantTypeReq::DeviceCode { .. } => Oauth2GrantType::DeviceCode,
+            GrantTypeReq::TokenExchange { .. } => Oauth2GrantType::TokenExchange,
+        };
+
+        if !o2rs.grant_type_allowed(grant_type) {
+            security_info!(
+                ?o2rs.name,
+                %grant_type,
+                "OAuth2 client is not permitted to use this grant type"
+            );
+            return Err(Oauth2Error::UnauthorizedClient);
+        }
+
         // We are authenticated! Yay! Now we can actually check things ...
         match &token_req.grant_type {
             GrantTypeReq::AuthorizationCode {
@@ -1072,11 +1735,12 @@ impl IdmServerProxyWriteTransaction<'_> {
                 code,
                 redirect_uri,
                 code_verifier.as_deref(),
+                cnf,
                 ct,
             ),
             GrantTypeReq::ClientCredentials { scope } => {
                 if client_authentication_valid {
-                    self.check_oauth2_token_client_credentials(&o2rs, scope.as_ref(), ct)
+                    self.check_oauth2_token_client_credentials(&o2rs, scope.as_ref(), cnf, ct)
                 } else {
                     security_info!(
                         "Unable to proceed with client credentials grant unless client authentication is provided and valid"
@@ -1087,20 +1751,379 @@ impl IdmServerProxyWriteTransaction<'_> {
             GrantTypeReq::RefreshToken {
                 refresh_token,
                 scope,
-            } => self.check_oauth2_token_refresh(&o2rs, refresh_token, scope.as_ref(), ct),
+            } => self.check_oauth2_token_refresh(&o2rs, refresh_token, scope.as_ref(), cnf, ct),
             GrantTypeReq::DeviceCode { device_code, scope } => {
                 self.check_oauth2_device_code_status(device_code, scope)
             }
+            GrantTypeReq::TokenExchange {
+                subject_token,
+                subject_token_type,
+                actor_token,
+                actor_token_type,
+                requested_token_type,
+                audience,
+                scope,
+            } => {
+                if !client_authentication_valid {
+                    security_info!(
+                        "Unable to proceed with token exchange unless client authentication is provided and valid"
+                    );
+                    return Err(Oauth2Error::AuthenticationRequired);
+                }
+
+                // The acting party is the authenticated client, so we don't accept a separate
+                // actor token.
+                if actor_token.is_some() || actor_token_type.is_some() {
+                    security_info!("Token exchange with an actor token is not supported");
+                    return Err(Oauth2Error::InvalidRequest);
+                }
+
+                self.check_oauth2_token_exchange_rfc8693(
+                    &o2rs,
+                    subject_token,
+                    subject_token_type,
+                    requested_token_type.as_deref(),
+                    audience.as_deref(),
+                    scope.as_ref(),
+                    cnf,
+                    ct,
+                )
+            }
         }
+        .inspect(|_| {
+            self.qs_write
+                .get_metrics()
+                .record_token_issued(grant_type.into())
+        })
     }
 
-    fn get_client(&self, client_id: &str) -> Result<Oauth2RS, Oauth2Error> {
-        let s = self
-            .oauth2rs
-            .inner
-            .rs_set
-            .get(client_id)
-            .ok_or_else(|| {
+    /// Register a new OAuth2 client from the metadata provided by RFC 7591 dynamic client
+    /// registration. The client is created as the requesting identity, so access controls
+    /// determine who may register clients.
+    #[instrument(level = "debug", skip_all)]
+    pub fn oauth2_client_register(
+        &mut self,
+        ident: &Identity,
+        reg_req: &ClientRegistrationRequest,
+        ct: Duration,
+    ) -> Result<ClientRegistrationResponse, Oauth2Error> {
+        let Some(landing_uri) = reg_req.redirect_uris.first() else {
+            admin_warn!("OAuth2 client registration requires at least one redirect_uri");
+            return Err(Oauth2Error::InvalidRedirectUri);
+        };
+
+        let application_type = reg_req.application_type.unwrap_or_default();
+        let native = application_type == ApplicationType::Native;
+
+        if let Some(redirect_uri) = reg_req
+            .redirect_uris
+            .iter()
+            .find(|uri| !check_registration_redirect_uri(uri, native))
+        {
+            admin_warn!(
+                %redirect_uri,
+                ?application_type,
+                "OAuth2 client registration redirect_uri is not valid"
+            );
+            return Err(Oauth2Error::InvalidRedirectUri);
+        }
+
+        let scope_maps = self.oauth2_client_register_scope_maps(ident, reg_req.scope.as_ref())?;
+
+        let (rs_class, token_endpoint_auth_method) = match reg_req.token_endpoint_auth_method {
+            // Native applications can't keep a secret, so they are always public.
+            None | Some(TokenEndpointAuthMethod::None)
+                if application_type == ApplicationType::Native =>
+            {
+                (
+                    EntryClass::OAuth2ResourceServerPublic,
+                    TokenEndpointAuthMethod::None,
+                )
+            }
+            Some(ref method) if application_type == ApplicationType::Native => {
+                admin_warn!(
+                    ?method,
+                    "Native OAuth2 clients must not authenticate to the token endpoint"
+                );
+                return Err(Oauth2Error::InvalidClientMetadata);
+            }
+            None | Some(TokenEndpointAuthMethod::ClientSecretBasic) => (
+                EntryClass::OAuth2ResourceServerBasic,
+                TokenEndpointAuthMethod::ClientSecretBasic,
+            ),
+            Some(TokenEndpointAuthMethod::ClientSecretPost) => (
+                EntryClass::OAuth2ResourceServerBasic,
+                TokenEndpointAuthMethod::ClientSecretPost,
+            ),
+            Some(TokenEndpointAuthMethod::None) => (
+                EntryClass::OAuth2ResourceServerPublic,
+                TokenEndpointAuthMethod::None,
+            ),
+            Some(ref method) => {
+                admin_warn!(
+                    ?method,
+                    "Unsupported OAuth2 client registration auth method"
+                );
+                return Err(Oauth2Error::InvalidClientMetadata);
+            }
+        };
+
+        let name = format!("client_{}", Uuid::new_v4().simple());
+
+        let displayname = reg_req
+            .client_name
+            .as_deref()
+            .map(str::trim)
+            .filter(|client_name| !client_name.is_empty())
+            .unwrap_or(name.as_str())
+            .to_string();
+
+        let mut entry = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (Attribute::Class, rs_class.to_value()),
+            (Attribute::Name, Value::new_iname(&name)),
+            (Attribute::DisplayName, Value::new_utf8s(&displayname)),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::Url(landing_uri.clone())
+            ),
+            (Attribute::OAuth2StrictRedirectUri, Value::Bool(true))
+        );
+
+        if application_type == ApplicationType::Native {
+            entry.add_ava(
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerNative.to_value(),
+            );
+        }
+
+        for redirect_uri in reg_req.redirect_uris.iter() {
+            entry.add_ava(Attribute::OAuth2RsOrigin, Value::Url(redirect_uri.clone()));
+        }
+
+        for (group_uuid, scopes) in scope_maps.iter() {
+            let scope_map = Value::new_oauthscopemap(*group_uuid, scopes.clone())
+                .ok_or(Oauth2Error::InvalidClientMetadata)?;
+            entry.add_ava(Attribute::OAuth2RsScopeMap, scope_map);
+        }
+
+        let ce = CreateEvent {
+            ident: ident.clone(),
+            entries: vec![entry],
+        };
+
+        self.qs_write.create(&ce).map_err(|err| match err {
+            OperationError::AccessDenied => {
+                security_info!(%ident, "Identity is not permitted to register OAuth2 clients");
+                Oauth2Error::AccessDenied
+            }
+            OperationError::SchemaViolation(_) | OperationError::Plugin(_) => {
+                admin_warn!(?err, "OAuth2 client registration metadata was rejected");
+                Oauth2Error::InvalidClientMetadata
+            }
+            err => Oauth2Error::ServerError(err),
+        })?;
+
+        // The secret is generated during creation, so read it back to return to the client.
+        let client_secret = if matches!(rs_class, EntryClass::OAuth2ResourceServerBasic) {
+            let entry = self
+                .qs_write
+                .internal_search(filter!(f_and!([
+                    f_eq(Attribute::Class, EntryClass::OAuth2ResourceServer.into()),
+                    f_eq(Attribute::Name, PartialValue::new_iname(&name))
+                ])))
+                .and_then(|mut entries| entries.pop().ok_or(OperationError::NoMatchingEntries))
+                .map_err(Oauth2Error::ServerError)?;
+
+            entry
+                .get_ava_single_secret(Attribute::OAuth2RsBasicSecret)
+                .map(str::to_string)
+                .map(Some)
+                .ok_or(Oauth2Error::ServerError(OperationError::InvalidValueState))?
+        } else {
+            None
+        };
+
+        let client_secret_expires_at = client_secret.as_ref().map(|_| 0);
+
+        security_info!(%ident, client_id = %name, "Registered OAuth2 client");
+
+        Ok(ClientRegistrationResponse {
+            client_id: name,
+            client_secret,
+            client_id_issued_at: ct.as_secs() as i64,
+            client_secret_expires_at,
+            redirect_uris: reg_req.redirect_uris.clone(),
+            token_endpoint_auth_method,
+            client_name: displayname,
+            application_type,
+            scope: reg_req.scope.clone(),
+        })
+    }
+
+    /// The scope maps of a client being registered. A registrar may only grant the scopes that
+    /// the scope maps of existing clients grant to its own groups, and only to those groups, so
+    /// that registration can't be used to grant access that was not already delegated.
+    fn oauth2_client_register_scope_maps(
+        &mut self,
+        ident: &Identity,
+        req_scopes: Option<&BTreeSet<String>>,
+    ) -> Result<BTreeMap<Uuid, BTreeSet<String>>, Oauth2Error> {
+        let Some(req_scopes) = req_scopes.filter(|scopes| !scopes.is_empty()) else {
+            return Ok(BTreeMap::new());
+        };
+
+        validate_scopes(req_scopes).map_err(|_| Oauth2Error::InvalidClientMetadata)?;
+
+        let memberof = ident.get_memberof().cloned().unwrap_or_default();
+
+        let mut scope_maps: BTreeMap<Uuid, BTreeSet<String>> = BTreeMap::new();
+
+        if !memberof.is_empty() {
+            let rs_entries = self
+                .qs_write
+                .internal_search(filter!(f_and!([
+                    f_eq(Attribute::Class, EntryClass::OAuth2ResourceServer.into()),
+                    f_or(
+                        memberof
+                            .iter()
+                            .map(|group_uuid| f_eq(
+                                Attribute::OAuth2RsScopeMap,
+                                PartialValue::Refer(*group_uuid)
+                            ))
+                            .collect()
+                    )
+                ])))
+                .map_err(Oauth2Error::ServerError)?;
+
+            for (group_uuid, scopes) in rs_entries
+                .iter()
+                .filter_map(|entry| entry.get_ava_as_oauthscopemaps(Attribute::OAuth2RsScopeMap))
+                .flat_map(|rs_scope_maps| rs_scope_maps.iter())
+                .filter(|(group_uuid, _)| memberof.contains(group_uuid))
+            {
+                let granted = scope_maps.entry(*group_uuid).or_default();
+                granted.extend(scopes.intersection(req_scopes).cloned());
+            }
+        }
+
+        scope_maps.retain(|_, scopes| !scopes.is_empty());
+
+        let permitted: BTreeSet<&String> = scope_maps.values().flatten().collect();
+        let denied: Vec<&String> = req_scopes
+            .iter()
+            .filter(|scope| !permitted.contains(scope))
+            .collect();
+
+        if !denied.is_empty() {
+            security_info!(
+                %ident,
+                ?denied,
+                "Identity is not permitted to register OAuth2 clients with these scopes"
+            );
+            return Err(Oauth2Error::InvalidClientMetadata);
+        }
+
+        Ok(scope_maps)
+    }
+
+    /// Replace the secret of the confidential client matched by `filter`. The replaced secret
+    /// remains valid for `previous_secret_validity` so that the client can be updated without
+    /// an outage. The secret is removed as `ident`, so access controls determine who may rotate
+    /// it, and the new secret is generated as the client is modified.
+    #[instrument(level = "debug", skip_all)]
+    pub fn oauth2_basic_secret_rotate(
+        &mut self,
+        ident: &Identity,
+        filter: &Filter<FilterInvalid>,
+        previous_secret_validity: Option<Duration>,
+        ct: Duration,
+    ) -> Result<(), OperationError> {
+        let filter = Filter::join_parts_and(
+            filter.clone(),
+            filter!(f_eq(
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.into()
+            )),
+        );
+
+        let mut entries = self.qs_write.internal_search(filter.clone())?;
+        let entry = match (entries.pop(), entries.is_empty()) {
+            (Some(entry), true) => entry,
+            (Some(_), false) => {
+                admin_warn!("Refusing to rotate the secret of multiple OAuth2 clients");
+                return Err(OperationError::InvalidRequestState);
+            }
+            (None, _) => return Err(OperationError::NoMatchingEntries),
+        };
+
+        let previous_secret = entry
+            .get_ava_single_secret(Attribute::OAuth2RsBasicSecret)
+            .map(str::to_string)
+            .ok_or(OperationError::InvalidValueState)?;
+        let previous_last_used =
+            entry.get_ava_single_datetime(Attribute::OAuth2RsBasicSecretLastUsed);
+        let previous_expiry = OffsetDateTime::UNIX_EPOCH
+            + ct
+            + previous_secret_validity
+                .unwrap_or_else(|| Duration::from_secs(OAUTH2_PREVIOUS_SECRET_VALIDITY));
+
+        // Removing the secret causes a new one to be generated. Any secret from an earlier
+        // rotation is discarded.
+        let ml = ModifyList::new_list(vec![
+            Modify::Purged(Attribute::OAuth2RsBasicSecret),
+            Modify::Purged(Attribute::OAuth2RsBasicSecretExpiry),
+            Modify::Purged(Attribute::OAuth2RsBasicSecretLastUsed),
+            Modify::Purged(Attribute::OAuth2RsBasicSecretPrevious),
+            Modify::Purged(Attribute::OAuth2RsBasicSecretPreviousExpiry),
+            Modify::Purged(Attribute::OAuth2RsBasicSecretPreviousLastUsed),
+        ]);
+
+        let me = ModifyEvent::from_internal_parts(ident.clone(), &ml, &filter, &self.qs_write)?;
+        self.qs_write.modify(&me)?;
+
+        let mut mods = vec![
+            Modify::Present(
+                Attribute::OAuth2RsBasicSecretPrevious,
+                Value::SecretValue(previous_secret),
+            ),
+            Modify::Present(
+                Attribute::OAuth2RsBasicSecretPreviousExpiry,
+                Value::new_datetime(previous_expiry),
+            ),
+        ];
+        if let Some(last_used) = previous_last_used {
+            mods.push(Modify::Present(
+                Attribute::OAuth2RsBasicSecretPreviousLastUsed,
+                Value::new_datetime(last_used),
+            ));
+        }
+
+        self.qs_write
+            .internal_modify_uuid(entry.get_uuid(), &ModifyList::new_list(mods))?;
+
+        security_info!(
+            %ident,
+            client_uuid = %entry.get_uuid(),
+            ?previous_expiry,
+            "Rotated OAuth2 client secret"
+        );
+
+        Ok(())
+    }
+
+    fn get_client(&self, client_id: &str) -> Result<Oauth2RS, Oauth2Error> {
+        let s = self
+            .oauth2rs
+            .inner
+            .rs_set
+            .get(client_id)
+            .ok_or_else(|| {
                 admin_warn!("Invalid OAuth2 client_id {}", client_id);
                 Oauth2Error::AuthenticationRequired
             })?
@@ -1279,6 +2302,7 @@ impl IdmServerProxyWriteTransaction<'_> {
         token_req_code: &str,
         token_req_redirect_uri: &Url,
         token_req_code_verifier: Option<&str>,
+        cnf: Option<OAuth2TokenConfirmation>,
         ct: Duration,
     ) -> Result<AccessTokenResponse, Oauth2Error> {
         // Check the token_req is within the valid time, and correctly signed for
@@ -1369,6 +2393,8 @@ impl IdmServerProxyWriteTransaction<'_> {
             parent_session_id,
             session_id,
             nonce,
+            None,
+            cnf,
         )
     }
 
@@ -1378,6 +2404,7 @@ impl IdmServerProxyWriteTransaction<'_> {
         o2rs: &Oauth2RS,
         refresh_token: &str,
         req_scopes: Option<&BTreeSet<String>>,
+        cnf: Option<OAuth2TokenConfirmation>,
         ct: Duration,
     ) -> Result<AccessTokenResponse, Oauth2Error> {
         // Validate the refresh token decrypts and it's expiry is within the valid window.
@@ -1410,12 +2437,20 @@ impl IdmServerProxyWriteTransaction<'_> {
                 iat,
                 nbf: _,
                 nonce,
+                actor,
+                cnf: refresh_cnf,
             } => {
                 if exp <= ct.as_secs() as i64 {
                     security_info!(?uuid, "refresh token has expired, ");
                     return Err(Oauth2Error::InvalidGrant);
                 }
 
+                // A bound refresh token can only be used with a proof from the same key.
+                if !cnf_is_satisfied(refresh_cnf.as_ref(), cnf.as_ref()) {
+                    security_info!(?uuid, "refresh token is bound to a different DPoP key");
+                    return Err(Oauth2Error::InvalidDpopProof);
+                }
+
                 // Check the session is still valid. This call checks the parent session
                 // and the OAuth2 session.
                 let valid = self
@@ -1450,19 +2485,51 @@ impl IdmServerProxyWriteTransaction<'_> {
 
                 // If the refresh token was issued previous to the time listed in our oauth2_session
                 // this indicates session desync / replay. We must nuke the session at this point.
+                // Clients that are unable to store rotated refresh tokens may opt out of this, in
+                // which case earlier refresh tokens remain valid until they expire.
                 //
                 // Need to think about how to handle this nicely give transactions.
-                if iat < oauth2_session.issued_at.unix_timestamp() {
+                if o2rs.refresh_token_rotation && iat < oauth2_session.issued_at.unix_timestamp() {
                     security_info!(
                         ?session_id,
-                        "Attempt to reuse a refresh token detected, destroying session"
+                        ?parent_session_id,
+                        "Attempt to reuse a refresh token detected, destroying session family"
                     );
 
-                    // Revoke it
-                    let modlist = ModifyList::new_list(vec![Modify::Removed(
-                        Attribute::OAuth2Session,
-                        PartialValue::Refer(session_id),
-                    )]);
+                    // Whoever holds the reused token may have already exchanged it, or the
+                    // access tokens issued with it, for other sessions. All sessions under the
+                    // same parent session are revoked, recording why so that it can be shown
+                    // in the session listing.
+                    let revoked_at = self.qs_write.get_cid().clone();
+                    let revoked_sessions: Vec<_> = entry
+                        .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+                        .into_iter()
+                        .flatten()
+                        .filter(|(family_session_id, family_session)| {
+                            **family_session_id == session_id
+                                || family_session.parent == Some(parent_session_id)
+                        })
+                        .filter(|(_, family_session)| {
+                            !matches!(family_session.state, SessionState::RevokedAt(_))
+                        })
+                        .map(|(family_session_id, family_session)| {
+                            Modify::Present(
+                                Attribute::OAuth2Session,
+                                Value::Oauth2Session(
+                                    *family_session_id,
+                                    Oauth2Session {
+                                        state: SessionState::RevokedAt(revoked_at.clone()),
+                                        revoke_reason: Some(
+                                            Oauth2SessionRevokeReason::RefreshTokenReuse,
+                                        ),
+                                        ..family_session.clone()
+                                    },
+                                ),
+                            )
+                        })
+                        .collect();
+
+                    let modlist = ModifyList::new_list(revoked_sessions);
 
                     self.qs_write
                         .internal_modify(
@@ -1507,16 +2574,237 @@ impl IdmServerProxyWriteTransaction<'_> {
                     parent_session_id,
                     session_id,
                     nonce,
+                    actor,
+                    cnf,
                 )
             }
         }
     }
 
+    /// Exchange a user's access token that was issued to this client for a new access token,
+    /// either with a reduced set of scopes, or for a different client that this client is
+    /// permitted to act on behalf of the user towards.
+    #[instrument(level = "debug", skip_all)]
+    fn check_oauth2_token_exchange_rfc8693(
+        &mut self,
+        o2rs: &Oauth2RS,
+        subject_token: &str,
+        subject_token_type: &str,
+        requested_token_type: Option<&str>,
+        audience: Option<&str>,
+        req_scopes: Option<&BTreeSet<String>>,
+        cnf: Option<OAuth2TokenConfirmation>,
+        ct: Duration,
+    ) -> Result<AccessTokenResponse, Oauth2Error> {
+        if subject_token_type != OAUTH2_TOKEN_TYPE_ACCESS_TOKEN {
+            security_info!(?subject_token_type, "Unsupported subject token type");
+            return Err(Oauth2Error::InvalidRequest);
+        }
+
+        if requested_token_type.is_some_and(|t| t != OAUTH2_TOKEN_TYPE_ACCESS_TOKEN) {
+            security_info!(?requested_token_type, "Unsupported requested token type");
+            return Err(Oauth2Error::InvalidRequest);
+        }
+
+        // Which client is the new token for? By default this is the requesting client.
+        let target_rs = match audience {
+            None => o2rs.clone(),
+            Some(aud) if aud == o2rs.name => o2rs.clone(),
+            Some(aud) => {
+                let target_rs = self.oauth2rs.inner.rs_set.get(aud).ok_or_else(|| {
+                    security_info!(?aud, "Token exchange audience is not a known client");
+                    Oauth2Error::InvalidTarget
+                })?;
+
+                if !o2rs.token_exchange_audiences.contains(&target_rs.uuid) {
+                    security_info!(
+                        client_id = %o2rs.name,
+                        audience = %target_rs.name,
+                        "Client is not permitted to exchange tokens for this audience"
+                    );
+                    return Err(Oauth2Error::InvalidTarget);
+                }
+
+                target_rs.clone()
+            }
+        };
+
+        // The subject token must be an access token that we issued to the requesting client.
+        let jwsc = JwsCompact::from_str(subject_token).map_err(|err| {
+            admin_error!(?err, "Unable to parse subject token");
+            Oauth2Error::InvalidRequest
+        })?;
+
+        let access_token = o2rs
+            .jws_verify(self.qs_write.get_key_providers(), &jwsc)
+            .map_err(|err| {
+                admin_error!(?err, "Unable to verify subject token");
+                Oauth2Error::InvalidRequest
+            })
+            .and_then(|jws| {
+                jws.from_json().map_err(|err| {
+                    admin_error!(?err, "Unable to deserialise subject token");
+                    Oauth2Error::InvalidRequest
+                })
+            })?;
+
+        let OAuth2RFC9068Token::<_> {
+            sub,
+            exp,
+            iat,
+            extensions:
+                OAuth2RFC9068TokenExtensions {
+                    scope: subject_scopes,
+                    session_id: subject_session_id,
+                    parent_session_id,
+                    act,
+                    cnf: subject_cnf,
+                    ..
+                },
+            ..
+        } = access_token;
+
+        if exp <= ct.as_secs() as i64 {
+            security_info!(?sub, "subject token has expired");
+            return Err(Oauth2Error::InvalidGrant);
+        }
+
+        // A bound subject token can only be exchanged with a proof from the same key.
+        if !cnf_is_satisfied(subject_cnf.as_ref(), cnf.as_ref()) {
+            security_info!(?sub, "subject token is bound to a different key");
+            return Err(Oauth2Error::InvalidDpopProof);
+        }
+
+        // Exchanged tokens can't be exchanged again, so that the acting party of a
+        // token is always the client that the user authorised.
+        if act.is_some() {
+            security_info!(?sub, "subject token was issued by a token exchange");
+            return Err(Oauth2Error::InvalidRequest);
+        }
+
+        let Some(parent_session_id) = parent_session_id else {
+            security_info!(?sub, "subject token has no parent session");
+            return Err(Oauth2Error::InvalidRequest);
+        };
+
+        // Check the account and the subject token's session are still valid.
+        let valid = self
+            .check_oauth2_account_uuid_valid(
+                sub,
+                subject_session_id,
+                Some(parent_session_id),
+                iat,
+                ct,
+            )
+            .map_err(|_| admin_error!("Account is not valid"));
+
+        let Ok(Some(entry)) = valid else {
+            security_info!(?sub, "subject token account is not valid");
+            return Err(Oauth2Error::InvalidGrant);
+        };
+
+        // When down-scoping the token for the same client, the new scopes must be a subset of
+        // the subject token. For another client, the user must be granted the scopes by the
+        // scope maps of that client.
+        let (avail_scopes, sup_scopes) = if target_rs.uuid == o2rs.uuid {
+            (subject_scopes, BTreeSet::default())
+        } else {
+            let member_of = entry
+                .get_ava_refer(Attribute::MemberOf)
+                .cloned()
+                .unwrap_or_default();
+
+            let scopes_for = |maps: &BTreeMap<Uuid, BTreeSet<String>>| {
+                maps.iter()
+                    .filter_map(|(u, m)| {
+                        if member_of.contains(u) {
+                            Some(m.iter())
+                        } else {
+                            None
+                        }
+                    })
+                    .flatten()
+                    .cloned()
+                    .collect::<BTreeSet<_>>()
+            };
+
+            (
+                scopes_for(&target_rs.scope_maps),
+                scopes_for(&target_rs.sup_scope_maps),
+            )
+        };
+
+        let granted_scopes = if let Some(req_scopes) = req_scopes {
+            validate_scopes(req_scopes)?;
+
+            if !req_scopes.is_subset(&avail_scopes) {
+                admin_warn!(
+                    requested_scopes = ?req_scopes,
+                    available_scopes = ?avail_scopes,
+                    "Token exchange requested scopes that are not available"
+                );
+                return Err(Oauth2Error::InvalidScope);
+            }
+
+            req_scopes.clone()
+        } else {
+            avail_scopes
+        };
+
+        if granted_scopes.is_empty() {
+            admin_warn!(audience = %target_rs.name, "Token exchange would grant no scopes");
+            return Err(Oauth2Error::InvalidScope);
+        }
+
+        let granted_scopes: BTreeSet<String> =
+            granted_scopes.into_iter().chain(sup_scopes).collect();
+
+        // ----------
+        // good to go
+
+        // The exchanged token is a new session for the target client, under the same parent
+        // session as the subject token so that it is revoked with the user's session.
+        let session_id = Uuid::new_v4();
+
+        let mut response = self.generate_access_token_response(
+            &target_rs,
+            ct,
+            granted_scopes.clone(),
+            sub,
+            parent_session_id,
+            session_id,
+            None,
+            Some(o2rs.name.clone()),
+            cnf,
+        )?;
+
+        response.issued_token_type = Some(OAUTH2_TOKEN_TYPE_ACCESS_TOKEN.to_string());
+
+        if self
+            .audit_tx
+            .send(AuditEvent::Oauth2TokenExchanged {
+                uuid: sub,
+                client_id: o2rs.name.clone(),
+                audience: target_rs.name.clone(),
+                subject_session_id,
+                session_id,
+                scopes: granted_scopes,
+                time: OffsetDateTime::UNIX_EPOCH + ct,
+            })
+            .is_err()
+        {
+            error!("Unable to submit audit event to queue");
+        }
+
+        Ok(response)
+    }
+
     #[instrument(level = "debug", skip_all)]
     fn check_oauth2_token_client_credentials(
         &mut self,
         o2rs: &Oauth2RS,
         req_scopes: Option<&BTreeSet<String>>,
+        cnf: Option<OAuth2TokenConfirmation>,
         ct: Duration,
     ) -> Result<AccessTokenResponse, Oauth2Error> {
         let req_scopes = req_scopes.cloned().unwrap_or_default();
@@ -1559,6 +2847,8 @@ impl IdmServerProxyWriteTransaction<'_> {
 
         let uuid = o2rs.uuid;
 
+        let token_type = access_token_type(cnf.as_ref());
+
         let access_token_raw = Oauth2TokenType::ClientAccess {
             scopes: granted_scopes,
             session_id,
@@ -1566,6 +2856,7 @@ impl IdmServerProxyWriteTransaction<'_> {
             exp,
             iat,
             nbf: iat,
+            cnf,
         };
 
         let access_token_data = serde_json::to_vec(&access_token_raw).map_err(|e| {
@@ -1585,6 +2876,7 @@ impl IdmServerProxyWriteTransaction<'_> {
                 state: SessionState::ExpiresAt(odt_exp),
                 issued_at: odt_ct,
                 rs_uuid: o2rs.uuid,
+                revoke_reason: None,
             },
         );
 
@@ -1602,16 +2894,34 @@ impl IdmServerProxyWriteTransaction<'_> {
                 Oauth2Error::ServerError(e)
             })?;
 
+        // Client credential grants have no user to attribute them to, so record
+        // them against the resource server for later review.
+        if self
+            .audit_tx
+            .send(AuditEvent::Oauth2ClientCredentialsGranted {
+                uuid,
+                client_id: o2rs.name.clone(),
+                session_id,
+                scopes: scope.clone(),
+                time: odt_ct,
+            })
+            .is_err()
+        {
+            error!("Unable to submit audit event to queue");
+        }
+
         Ok(AccessTokenResponse {
             access_token,
-            token_type: AccessTokenType::Bearer,
+            token_type,
             expires_in,
             refresh_token: None,
             scope,
             id_token: None,
+            issued_token_type: None,
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn generate_access_token_response(
         &mut self,
         o2rs: &Oauth2RS,
@@ -1622,6 +2932,8 @@ impl IdmServerProxyWriteTransaction<'_> {
         parent_session_id: Uuid,
         session_id: Uuid,
         nonce: Option<String>,
+        actor: Option<String>,
+        cnf: Option<OAuth2TokenConfirmation>,
     ) -> Result<AccessTokenResponse, Oauth2Error> {
         let odt_ct = OffsetDateTime::UNIX_EPOCH + ct;
         let iat = ct.as_secs() as i64;
@@ -1635,10 +2947,11 @@ impl IdmServerProxyWriteTransaction<'_> {
         // expiries are *purely* for the tokens we issue and are *not related* to the expiries of the
         // the session - these are enforced as above!
 
-        let expiry = odt_ct + Duration::from_secs(OAUTH2_ACCESS_TOKEN_EXPIRY as u64);
-        let expires_in = OAUTH2_ACCESS_TOKEN_EXPIRY;
-        let refresh_expiry = iat + OAUTH_REFRESH_TOKEN_EXPIRY as i64;
-        let odt_refresh_expiry = odt_ct + Duration::from_secs(OAUTH_REFRESH_TOKEN_EXPIRY);
+        let expires_in = o2rs.access_token_expiry();
+        let expiry = odt_ct + Duration::from_secs(expires_in as u64);
+        let refresh_token_expiry = o2rs.refresh_token_expiry();
+        let refresh_expiry = iat + refresh_token_expiry as i64;
+        let odt_refresh_expiry = odt_ct + Duration::from_secs(refresh_token_expiry);
 
         let scope = scopes.clone();
 
@@ -1664,8 +2977,6 @@ impl IdmServerProxyWriteTransaction<'_> {
             // TODO: Can the user consent to which claims are released? Today as we don't support most
             // of them anyway, no, but in the future, we can stash these to the consent req.
 
-            // TODO: If max_age was requested in the request, we MUST provide auth_time.
-
             // amr == auth method
             // We removed this from uat, and I think that it's okay here. AMR is a bit useless anyway
             // since there is no standard for what it should look like wrt to cred strength.
@@ -1676,13 +2987,26 @@ impl IdmServerProxyWriteTransaction<'_> {
                 Err(err) => return Err(Oauth2Error::ServerError(err)),
             };
 
-            let account = match Account::try_from_entry_rw(&entry, &mut self.qs_write) {
+            let mut account = match Account::try_from_entry_rw(&entry, &mut self.qs_write) {
                 Ok(account) => account,
                 Err(err) => return Err(Oauth2Error::ServerError(err)),
             };
+            account.remove_lapsed_groups(ct);
 
             let s_claims = s_claims_for_account(o2rs, &account, &scopes);
-            let extra_claims = extra_claims_for_account(&account, &o2rs.claim_map, &scopes);
+            let extra_claims = extra_claims_for_account(
+                &entry,
+                &account,
+                &o2rs.claim_map,
+                &o2rs.computed_claims,
+                &scopes,
+            );
+
+            // When the user authenticated, so that clients that request a max_age can check it.
+            let auth_time = entry
+                .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+                .and_then(|sessions| sessions.get(&parent_session_id))
+                .map(|session| session.issued_at.unix_timestamp());
 
             let oidc = OidcToken {
                 iss: iss.clone(),
@@ -1691,7 +3015,7 @@ impl IdmServerProxyWriteTransaction<'_> {
                 iat,
                 nbf: Some(iat),
                 exp,
-                auth_time: None,
+                auth_time,
                 nonce: nonce.clone(),
                 at_hash: None,
                 acr: None,
@@ -1705,15 +3029,29 @@ impl IdmServerProxyWriteTransaction<'_> {
             trace!(?oidc);
 
             let jwt_signed = match &o2rs.jws_signer {
-                Oauth2JwsSigner::ES256 { signer } => signer.sign(&oidc),
-                Oauth2JwsSigner::RS256 { signer } => signer.sign(&oidc),
+                Oauth2JwsSigner::ES256 { signer } => signer.sign(&oidc).map(|jwt| jwt.to_string()),
+                Oauth2JwsSigner::RS256 { signer } => signer.sign(&oidc).map(|jwt| jwt.to_string()),
+                Oauth2JwsSigner::KeyObject { .. } => {
+                    let oidc = JwsBuilder::into_json(&oidc)
+                        .map(|builder| builder.set_typ(Some("JWT")).build())
+                        .map_err(|e| {
+                            admin_error!(err = ?e, "Unable to encode uat data");
+                            Oauth2Error::ServerError(OperationError::InvalidState)
+                        })?;
+
+                    let jwt_signed = o2rs
+                        .jws_sign(self.qs_write.get_key_providers(), &oidc, ct)
+                        .map_err(Oauth2Error::ServerError)?;
+
+                    Ok(jwt_signed.to_string())
+                }
             }
             .map_err(|e| {
                 admin_error!(err = ?e, "Unable to encode uat data");
                 Oauth2Error::ServerError(OperationError::InvalidState)
             })?;
 
-            Some(jwt_signed.to_string())
+            Some(jwt_signed)
         } else {
             // id_token is not required in non-openid flows.
             None
@@ -1737,6 +3075,8 @@ impl IdmServerProxyWriteTransaction<'_> {
                 nonce: nonce.clone(),
                 session_id,
                 parent_session_id: Some(parent_session_id),
+                act: actor.clone().map(|sub| OAuth2TokenActor { sub }),
+                cnf: cnf.clone(),
             },
         };
 
@@ -1747,14 +3087,18 @@ impl IdmServerProxyWriteTransaction<'_> {
                 Oauth2Error::ServerError(OperationError::InvalidState)
             })?;
 
-        let access_token = match &o2rs.jws_signer {
-            Oauth2JwsSigner::ES256 { signer } => signer.sign(&access_token_data),
-            Oauth2JwsSigner::RS256 { signer } => signer.sign(&access_token_data),
-        }
-        .map_err(|e| {
-            admin_error!(err = ?e, "Unable to sign access token data");
-            Oauth2Error::ServerError(OperationError::InvalidState)
-        })?;
+        let access_token = o2rs
+            .jws_sign(self.qs_write.get_key_providers(), &access_token_data, ct)
+            .map_err(|e| {
+                admin_error!(err = ?e, "Unable to sign access token data");
+                Oauth2Error::ServerError(OperationError::InvalidState)
+            })?;
+
+        let refresh_jkt = cnf.as_ref().and_then(|cnf| cnf.jkt.clone());
+        let refresh_cnf = refresh_jkt.map(|jkt| OAuth2TokenConfirmation {
+            jkt: Some(jkt),
+            x5t_s256: None,
+        });
 
         let refresh_token_raw = Oauth2TokenType::Refresh {
             scopes,
@@ -1765,6 +3109,8 @@ impl IdmServerProxyWriteTransaction<'_> {
             iat,
             nbf: iat,
             nonce,
+            actor,
+            cnf: refresh_cnf,
         };
 
         let refresh_token_data = serde_json::to_vec(&refresh_token_raw).map_err(|e| {
@@ -1785,6 +3131,7 @@ impl IdmServerProxyWriteTransaction<'_> {
                 state: SessionState::ExpiresAt(odt_refresh_expiry),
                 issued_at: odt_ct,
                 rs_uuid: o2rs.uuid,
+                revoke_reason: None,
             },
         );
 
@@ -1806,13 +3153,16 @@ impl IdmServerProxyWriteTransaction<'_> {
                 Oauth2Error::ServerError(e)
             })?;
 
+        let token_type = access_token_type(cnf.as_ref());
+
         Ok(AccessTokenResponse {
             access_token: access_token.to_string(),
-            token_type: AccessTokenType::Bearer,
+            token_type,
             expires_in,
             refresh_token: Some(refresh_token),
             scope,
             id_token,
+            issued_token_type: None,
         })
     }
 
@@ -1840,7 +3190,7 @@ impl IdmServerProxyWriteTransaction<'_> {
 
         // check the secret.
         if let OauthRSType::Basic { authz_secret, .. } = &o2rs.type_ {
-            if o2rs.is_basic() && authz_secret != &secret {
+            if o2rs.is_basic() && authz_secret.secret != secret {
                 security_info!("Invalid OAuth2 secret for client_id={}", client_id);
                 return Err(OperationError::InvalidSessionState);
             }
@@ -1927,46 +3277,16 @@ impl IdmServerProxyReadTransaction<'_> {
                 Oauth2Error::InvalidClientId
             })?;
 
-        // redirect_uri must be part of the client_id origins, unless the client is public and then it MAY
-        // be a loopback address exempting it from this check and enforcement and we can carry on safely.
-        if o2rs.type_.allow_localhost_redirect() && check_is_loopback(&auth_req.redirect_uri) {
-            debug!("Loopback redirect_uri detected, allowing for localhost");
-        } else {
-            // The legacy origin match is in use.
-            let origin_uri_matched =
-                !o2rs.strict_redirect_uri && o2rs.origins.contains(&auth_req.redirect_uri.origin());
-            // Strict uri validation is in use.
-            let strict_redirect_uri_matched =
-                o2rs.strict_redirect_uri && o2rs.redirect_uris.contains(&auth_req.redirect_uri);
-            // Allow opaque origins such as app uris.
-            let opaque_origin_matched = o2rs.opaque_origins.contains(&auth_req.redirect_uri);
-
-            // At least one of these conditions must hold true to proceed.
-            if !(strict_redirect_uri_matched || origin_uri_matched || opaque_origin_matched) {
-                if o2rs.strict_redirect_uri {
-                    warn!(
-                                "Invalid OAuth2 redirect_uri (must be an exact match to a redirect-url) - got {}",
-                                auth_req.redirect_uri.as_str()
-                            );
-                } else {
-                    warn!(
-                        "Invalid OAuth2 redirect_uri (must be related to origin) - got {:?}",
-                        auth_req.redirect_uri.origin()
-                    );
-                }
-                return Err(Oauth2Error::InvalidOrigin);
-            }
-            // We have to specifically match on http here because non-http origins may be exempt from this
-            // enforcement.
-            if (o2rs.origin_https_required && auth_req.redirect_uri.scheme() != "https")
-                && !opaque_origin_matched
-            {
-                admin_warn!(
-                    "Invalid OAuth2 redirect_uri scheme (must be https for secure origin) - got {}",
-                    auth_req.redirect_uri.to_string()
-                );
-                return Err(Oauth2Error::InvalidOrigin);
-            }
+        o2rs.check_redirect_uri(&auth_req.redirect_uri)
+            .map_err(Oauth2Error::RedirectUriRejected)?;
+
+        if !o2rs.response_type_allowed(&auth_req.response_type) {
+            security_info!(
+                ?o2rs.name,
+                response_type = ?auth_req.response_type,
+                "OAuth2 client is not permitted to use this response type"
+            );
+            return Err(Oauth2Error::UnauthorizedClient);
         }
 
         let code_challenge = if let Some(pkce_request) = &auth_req.pkce_request {
@@ -1996,15 +3316,30 @@ impl IdmServerProxyReadTransaction<'_> {
         // Are we going to provide the functions for these? Most of these can be "later".
         // IF CHANGED: Update OidcDiscoveryResponse!!!
 
-        // TODO: https://openid.net/specs/openid-connect-basic-1_0.html#RequestParameters
-        // prompt - if set to login, we need to force a re-auth. But we don't want to
-        // if the user "only just" logged in, that's annoying. So we need a time window for
-        // this, to detect when we should force it to the consent req.
+        let prompt = OidcPrompt::parse(auth_req.oidc_ext.prompt.as_deref())?;
 
-        // TODO: display = popup vs touch vs wap etc.
+        let max_age = auth_req
+            .max_age
+            .map(|max_age| {
+                u64::try_from(max_age).map_err(|_| {
+                    warn!(?max_age, "Invalid max_age, must not be negative");
+                    Oauth2Error::InvalidRequest
+                })
+            })
+            .transpose()?;
+
+        // Once the redirect uri is validated, errors caused by the prompt parameter are
+        // returned to the client.
+        let reject = |error| {
+            Ok(AuthoriseResponse::Rejected(AuthoriseReject {
+                redirect_uri: auth_req.redirect_uri.clone(),
+                state: auth_req.state.clone(),
+                response_mode,
+                error,
+            }))
+        };
 
-        // TODO: max_age, pass through with consent req. If 0, force login.
-        // Otherwise force a login re the uat timeout.
+        // TODO: display = popup vs touch vs wap etc.
 
         // TODO: ui_locales / claims_locales for the ui. Only if we don't have a Uat that
         // would provide this.
@@ -2012,6 +3347,10 @@ impl IdmServerProxyReadTransaction<'_> {
         // TODO: id_token_hint - a past token which can be used as a hint.
 
         let Some(ident) = maybe_ident else {
+            if prompt.none {
+                security_info!("No identity available, and the client requested no prompt");
+                return reject(Oauth2Error::LoginRequired);
+            }
             debug!("No identity available, assume authentication required");
             return Ok(AuthoriseResponse::AuthenticationRequired {
                 client_name: o2rs.displayname.clone(),
@@ -2019,6 +3358,33 @@ impl IdmServerProxyReadTransaction<'_> {
             });
         };
 
+        // If the client requires a recent authentication, check when this session was
+        // authenticated. If we can't tell, the user must authenticate again.
+        let auth_expired = max_age.is_some_and(|max_age| {
+            ident
+                .get_session()
+                .map(|session| {
+                    let auth_age = (OffsetDateTime::UNIX_EPOCH + ct) - session.issued_at;
+                    auth_age > time::Duration::seconds(max_age as i64)
+                })
+                .unwrap_or(true)
+        });
+
+        if prompt.login || auth_expired {
+            if prompt.none {
+                security_info!("Authentication is required, and the client requested no prompt");
+                return reject(Oauth2Error::LoginRequired);
+            }
+            debug!(
+                prompt_login = prompt.login,
+                auth_expired, "Re-authentication required"
+            );
+            return Ok(AuthoriseResponse::AuthenticationRequired {
+                client_name: o2rs.displayname.clone(),
+                login_hint: auth_req.oidc_ext.login_hint.clone(),
+            });
+        }
+
         let Some(account_uuid) = ident.get_uuid() else {
             error!("Consent request ident does not have a valid UUID, unable to proceed");
             return Err(Oauth2Error::InvalidRequest);
@@ -2032,6 +3398,16 @@ impl IdmServerProxyReadTransaction<'_> {
             return Err(Oauth2Error::AccessDenied);
         }
 
+        // If the client is assigned to specific accounts or groups, this ident must be one of them.
+        if !o2rs.is_assigned(ident) {
+            admin_warn!(
+                %ident,
+                client_id = %o2rs.name,
+                "Identity is not assigned to this client"
+            );
+            return Err(Oauth2Error::ClientNotAssigned);
+        }
+
         // scopes - you need to have every requested scope or this auth_req is denied.
         let req_scopes: BTreeSet<String> = auth_req.scope.clone();
 
@@ -2119,7 +3495,7 @@ impl IdmServerProxyReadTransaction<'_> {
 
         let session_id = ident.get_session_id();
 
-        if consent_previously_granted {
+        if consent_previously_granted && !prompt.consent {
             if event_enabled!(tracing::Level::DEBUG) {
                 let pretty_scopes: Vec<String> =
                     granted_scopes.iter().map(|s| s.to_owned()).collect();
@@ -2153,6 +3529,9 @@ impl IdmServerProxyReadTransaction<'_> {
                 code,
                 response_mode,
             }))
+        } else if prompt.none {
+            security_info!("Consent is required, and the client requested no prompt");
+            reject(Oauth2Error::ConsentRequired)
         } else {
             //  Check that the scopes are the same as a previous consent (if any)
             // If oidc, what PII is visible?
@@ -2208,10 +3587,27 @@ impl IdmServerProxyReadTransaction<'_> {
                 .fernet
                 .encrypt_at_time(&consent_data, ct.as_secs());
 
+            let scope_descriptions = o2rs
+                .scope_descriptions
+                .iter()
+                .filter(|desc| granted_scopes.contains(desc.scope.as_str()))
+                .cloned()
+                .collect();
+
+            let claims = o2rs
+                .claim_map
+                .iter()
+                .filter(|(group_uuid, _)| ident.is_memberof(**group_uuid))
+                .flat_map(|(_, claims)| claims.iter().map(|(name, _)| name.clone()))
+                .chain(o2rs.computed_claims.iter().map(|c| c.claim.clone()))
+                .collect();
+
             Ok(AuthoriseResponse::ConsentRequested {
                 client_name: o2rs.displayname.clone(),
                 scopes: granted_scopes.into_iter().collect(),
                 pii_scopes,
+                scope_descriptions,
+                claims,
                 consent_token,
             })
         }
@@ -2267,7 +3663,9 @@ impl IdmServerProxyReadTransaction<'_> {
         // All good, now confirm the rejection to the client application.
         Ok(AuthoriseReject {
             redirect_uri: consent_req.redirect_uri,
+            state: consent_req.state,
             response_mode: consent_req.response_mode,
+            error: Oauth2Error::AccessDenied,
         })
     }
 
@@ -2291,10 +3689,10 @@ impl IdmServerProxyReadTransaction<'_> {
             Oauth2Error::AuthenticationRequired
         })?;
 
-        // check the secret.
+        // check the secret. Introspection is a read, so the use of the secret isn't recorded.
         match &o2rs.type_ {
-            OauthRSType::Basic { authz_secret, .. } => {
-                if authz_secret != &secret {
+            OauthRSType::Basic { .. } => {
+                if o2rs.type_.check_basic_secret(&secret, ct).is_none() {
                     security_info!("Invalid OAuth2 client_id secret");
                     return Err(Oauth2Error::AuthenticationRequired);
                 }
@@ -2308,24 +3706,18 @@ impl IdmServerProxyReadTransaction<'_> {
         let prefer_short_username = o2rs.prefer_short_username;
 
         if let Ok(jwsc) = JwsCompact::from_str(&intr_req.token) {
-            let access_token = match &o2rs.jws_signer {
-                Oauth2JwsSigner::ES256 { signer } => signer
-                    .get_verifier()
-                    .and_then(|verifier| verifier.verify(&jwsc)),
-                Oauth2JwsSigner::RS256 { signer } => signer
-                    .get_verifier()
-                    .and_then(|verifier| verifier.verify(&jwsc)),
-            }
-            .map_err(|err| {
-                admin_error!(?err, "Unable to verify access token");
-                Oauth2Error::InvalidRequest
-            })
-            .and_then(|jws| {
-                jws.from_json().map_err(|err| {
-                    admin_error!(?err, "Unable to deserialise access token");
+            let access_token = o2rs
+                .jws_verify(self.qs_read.get_key_providers(), &jwsc)
+                .map_err(|err| {
+                    admin_error!(?err, "Unable to verify access token");
                     Oauth2Error::InvalidRequest
                 })
-            })?;
+                .and_then(|jws| {
+                    jws.from_json().map_err(|err| {
+                        admin_error!(?err, "Unable to deserialise access token");
+                        Oauth2Error::InvalidRequest
+                    })
+                })?;
 
             let OAuth2RFC9068Token::<_> {
                 iss: _,
@@ -2345,6 +3737,8 @@ impl IdmServerProxyReadTransaction<'_> {
                         nonce: _,
                         session_id,
                         parent_session_id,
+                        act: _,
+                        cnf,
                     },
             } = access_token;
 
@@ -2367,10 +3761,11 @@ impl IdmServerProxyReadTransaction<'_> {
                 return Ok(AccessTokenIntrospectResponse::inactive());
             };
 
-            let account = match Account::try_from_entry_ro(&entry, &mut self.qs_read) {
+            let mut account = match Account::try_from_entry_ro(&entry, &mut self.qs_read) {
                 Ok(account) => account,
                 Err(err) => return Err(Oauth2Error::ServerError(err)),
             };
+            account.remove_lapsed_groups(ct);
 
             // ==== good to generate response ====
 
@@ -2382,7 +3777,7 @@ impl IdmServerProxyReadTransaction<'_> {
                 Some(account.spn.clone())
             };
 
-            let token_type = Some(AccessTokenType::Bearer);
+            let token_type = Some(access_token_type(cnf.as_ref()));
             Ok(AccessTokenIntrospectResponse {
                 active: true,
                 scope,
@@ -2396,6 +3791,7 @@ impl IdmServerProxyReadTransaction<'_> {
                 aud: Some(client_id),
                 iss: None,
                 jti: None,
+                cnf,
             })
         } else {
             let token: Oauth2TokenType = o2rs
@@ -2420,6 +3816,7 @@ impl IdmServerProxyReadTransaction<'_> {
                     exp,
                     iat,
                     nbf,
+                    cnf,
                 } => {
                     // Has this token expired?
                     if exp <= ct.as_secs() as i64 {
@@ -2442,7 +3839,7 @@ impl IdmServerProxyReadTransaction<'_> {
 
                     let scope = scopes.clone();
 
-                    let token_type = Some(AccessTokenType::Bearer);
+                    let token_type = Some(access_token_type(cnf.as_ref()));
 
                     let username = if prefer_short_username {
                         entry
@@ -2465,6 +3862,7 @@ impl IdmServerProxyReadTransaction<'_> {
                         aud: Some(client_id),
                         iss: None,
                         jti: None,
+                        cnf,
                     })
                 }
                 Oauth2TokenType::Refresh { .. } => Ok(AccessTokenIntrospectResponse::inactive()),
@@ -2477,6 +3875,8 @@ impl IdmServerProxyReadTransaction<'_> {
         &mut self,
         client_id: &str,
         token: JwsCompact,
+        dpop: Option<&DPoPProof>,
+        client_cert: Option<&ClientCertInfo>,
         ct: Duration,
     ) -> Result<OidcToken, Oauth2Error> {
         // DANGER: Why do we have to do this? During the use of qs for internal search
@@ -2495,24 +3895,18 @@ impl IdmServerProxyReadTransaction<'_> {
             &*(s as *const _)
         };
 
-        let access_token = match &o2rs.jws_signer {
-            Oauth2JwsSigner::ES256 { signer } => signer
-                .get_verifier()
-                .and_then(|verifier| verifier.verify(&token)),
-            Oauth2JwsSigner::RS256 { signer } => signer
-                .get_verifier()
-                .and_then(|verifier| verifier.verify(&token)),
-        }
-        .map_err(|err| {
-            admin_error!(?err, "Unable to verify access token");
-            Oauth2Error::InvalidRequest
-        })
-        .and_then(|jws| {
-            jws.from_json().map_err(|err| {
-                admin_error!(?err, "Unable to deserialise access token");
+        let access_token = o2rs
+            .jws_verify(self.qs_read.get_key_providers(), &token)
+            .map_err(|err| {
+                admin_error!(?err, "Unable to verify access token");
                 Oauth2Error::InvalidRequest
             })
-        })?;
+            .and_then(|jws| {
+                jws.from_json().map_err(|err| {
+                    admin_error!(?err, "Unable to deserialise access token");
+                    Oauth2Error::InvalidRequest
+                })
+            })?;
 
         let OAuth2RFC9068Token::<_> {
             iss: _,
@@ -2532,6 +3926,8 @@ impl IdmServerProxyReadTransaction<'_> {
                     nonce,
                     session_id,
                     parent_session_id,
+                    act: _,
+                    cnf,
                 },
         } = access_token;
         // Has this token expired?
@@ -2540,6 +3936,45 @@ impl IdmServerProxyReadTransaction<'_> {
             return Err(Oauth2Error::InvalidToken);
         }
 
+        // A bound token must be presented with a proof from the key it is bound to.
+        if let Some(jkt) = cnf.as_ref().and_then(|cnf| cnf.jkt.as_ref()) {
+            let Some(dpop) = dpop else {
+                security_info!(
+                    ?sub,
+                    "DPoP bound access token was presented without a proof"
+                );
+                return Err(Oauth2Error::InvalidToken);
+            };
+
+            let proof_cnf =
+                dpop.verify(&self.oauth2rs.inner.origin, Some(&token.to_string()), ct)?;
+
+            if proof_cnf.jkt.as_ref() != Some(jkt) {
+                security_info!(?sub, "DPoP proof was signed by a different key");
+                return Err(Oauth2Error::InvalidDpopProof);
+            }
+        }
+
+        // A certificate bound token must be presented over a connection authenticated
+        // with the same certificate.
+        if let Some(x5t_s256) = cnf.as_ref().and_then(|cnf| cnf.x5t_s256.as_ref()) {
+            let Some(client_cert) = client_cert else {
+                security_info!(
+                    ?sub,
+                    "certificate bound access token was presented without a client certificate"
+                );
+                return Err(Oauth2Error::InvalidToken);
+            };
+
+            if &client_cert_thumbprint(client_cert)? != x5t_s256 {
+                security_info!(
+                    ?sub,
+                    "access token is bound to a different client certificate"
+                );
+                return Err(Oauth2Error::InvalidToken);
+            }
+        }
+
         // Is the user expired, or the OAuth2 session invalid?
         let valid = self
             .check_oauth2_account_uuid_valid(sub, session_id, parent_session_id, iat, ct)
@@ -2553,17 +3988,24 @@ impl IdmServerProxyReadTransaction<'_> {
             return Err(Oauth2Error::InvalidToken);
         };
 
-        let account = match Account::try_from_entry_ro(&entry, &mut self.qs_read) {
+        let mut account = match Account::try_from_entry_ro(&entry, &mut self.qs_read) {
             Ok(account) => account,
             Err(err) => return Err(Oauth2Error::ServerError(err)),
         };
+        account.remove_lapsed_groups(ct);
 
         let amr = None;
 
         let iss = o2rs.iss.clone();
 
         let s_claims = s_claims_for_account(o2rs, &account, &scopes);
-        let extra_claims = extra_claims_for_account(&account, &o2rs.claim_map, &scopes);
+        let extra_claims = extra_claims_for_account(
+            &entry,
+            &account,
+            &o2rs.claim_map,
+            &o2rs.computed_claims,
+            &scopes,
+        );
 
         // ==== good to generate response ====
 
@@ -2653,6 +4095,8 @@ impl IdmServerProxyReadTransaction<'_> {
             introspection_endpoint,
             introspection_endpoint_auth_methods_supported,
             introspection_endpoint_auth_signing_alg_values_supported: None,
+            dpop_signing_alg_values_supported: Some(vec![IdTokenSignAlg::ES256]),
+            tls_client_certificate_bound_access_tokens: Some(true),
             code_challenge_methods_supported,
         })
     }
@@ -2685,10 +4129,7 @@ impl IdmServerProxyReadTransaction<'_> {
 
         let subject_types_supported = vec![SubjectType::Public];
 
-        let id_token_signing_alg_values_supported = match &o2rs.jws_signer {
-            Oauth2JwsSigner::ES256 { .. } => vec![IdTokenSignAlg::ES256],
-            Oauth2JwsSigner::RS256 { .. } => vec![IdTokenSignAlg::RS256],
-        };
+        let id_token_signing_alg_values_supported = vec![o2rs.id_token_sign_alg()];
 
         let userinfo_signing_alg_values_supported = None;
         let token_endpoint_auth_methods_supported = vec![
@@ -2767,6 +4208,8 @@ impl IdmServerProxyReadTransaction<'_> {
             introspection_endpoint,
             introspection_endpoint_auth_methods_supported,
             introspection_endpoint_auth_signing_alg_values_supported: None,
+            dpop_signing_alg_values_supported: Some(vec![IdTokenSignAlg::ES256]),
+            tls_client_certificate_bound_access_tokens: Some(true),
             device_authorization_endpoint: o2rs.device_authorization_endpoint.clone(),
         })
     }
@@ -2818,18 +4261,65 @@ impl IdmServerProxyReadTransaction<'_> {
             OperationError::NoMatchingEntries
         })?;
 
-        match &o2rs.jws_signer {
-            Oauth2JwsSigner::ES256 { signer } => signer.public_key_as_jwk(),
-            Oauth2JwsSigner::RS256 { signer } => signer.public_key_as_jwk(),
-        }
-        .map_err(|e| {
-            admin_error!("Unable to retrieve public key for {} - {:?}", o2rs.name, e);
-            OperationError::InvalidState
-        })
-        .map(|jwk| JwkKeySet { keys: vec![jwk] })
+        o2rs.public_jwks(self.qs_read.get_key_providers())
+            .map(|keys| JwkKeySet { keys })
+    }
+
+    /// List the OAuth2 sessions held by an entry. For a resource server this is the set
+    /// of sessions issued by the client credentials grant, and for an account this is
+    /// the set of sessions it holds with any resource server.
+    #[instrument(level = "debug", skip_all)]
+    pub fn oauth2_list_sessions(
+        &mut self,
+        lse: &ListOauth2SessionEvent,
+    ) -> Result<Vec<Oauth2SessionStatus>, OperationError> {
+        let srch =
+            SearchEvent::from_target_uuid_request(lse.ident.clone(), lse.target, &self.qs_read)
+                .inspect_err(|err| {
+                    admin_error!(?err, "Failed to begin oauth2 list sessions");
+                })?;
+
+        let mut entries = self.qs_read.search_ext(&srch)?;
+
+        let Some(entry) = entries.pop() else {
+            return Ok(Vec::with_capacity(0));
+        };
+
+        let sessions = entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .map(|smap| {
+                smap.iter()
+                    .map(|(session_id, session)| {
+                        let state = match session.state {
+                            SessionState::ExpiresAt(odt) => UatStatusState::ExpiresAt(odt),
+                            SessionState::NeverExpires => UatStatusState::NeverExpires,
+                            SessionState::RevokedAt(_) => UatStatusState::Revoked,
+                        };
+
+                        Oauth2SessionStatus {
+                            session_id: *session_id,
+                            rs_uuid: session.rs_uuid,
+                            parent_session_id: session.parent,
+                            state,
+                            issued_at: session.issued_at,
+                            revoke_reason: session.revoke_reason.map(|reason| reason.into()),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(sessions)
     }
 }
 
+pub struct ListOauth2SessionEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Who is it targeting?
+    pub target: Uuid,
+}
+
 fn parse_basic_authz(client_authz: &str) -> Result<(String, String), Oauth2Error> {
     // Check the client_authz
     let authz = general_purpose::STANDARD
@@ -2893,10 +4383,82 @@ fn s_claims_for_account(
     }
 }
 
+fn computed_claim_values(
+    entry: &EntrySealedCommitted,
+    account: &Account,
+    computed_claim: &Oauth2ComputedClaim,
+) -> Vec<String> {
+    let values = match &computed_claim.source {
+        Oauth2ComputedClaimSource::Template(parts) => {
+            let mut values = vec![String::new()];
+            for part in parts {
+                match part {
+                    Oauth2ClaimTemplatePart::Text(text) => {
+                        values.iter_mut().for_each(|v| v.push_str(text));
+                    }
+                    Oauth2ClaimTemplatePart::Attribute(attr) => {
+                        let attr_values: Vec<String> = entry
+                            .get_ava_set(Attribute::from(attr.as_str()))
+                            .map(|vs| vs.to_proto_string_clone_iter().collect())
+                            .unwrap_or_default();
+
+                        // Each value of a multivalued attribute produces its own claim value.
+                        values = values
+                            .iter()
+                            .flat_map(|v| attr_values.iter().map(move |av| format!("{v}{av}")))
+                            .collect();
+                    }
+                }
+            }
+            values
+        }
+        Oauth2ComputedClaimSource::GroupNames => {
+            account.groups.iter().map(|g| group_name(g.spn())).collect()
+        }
+    };
+
+    let values = values.into_iter().filter_map(|value| {
+        computed_claim
+            .transforms
+            .iter()
+            .try_fold(value, |value, transform| match transform {
+                Oauth2ClaimTransform::Lowercase => Some(value.to_lowercase()),
+                Oauth2ClaimTransform::StripPrefix(prefix) => Some(
+                    value
+                        .strip_prefix(prefix.as_str())
+                        .map(str::to_string)
+                        .unwrap_or(value),
+                ),
+                Oauth2ClaimTransform::FilterPrefix(prefix) => {
+                    value.starts_with(prefix.as_str()).then_some(value)
+                }
+                Oauth2ClaimTransform::FilterGlob(pattern) => {
+                    Oauth2ClaimTransform::glob_matches(pattern, &value).then_some(value)
+                }
+            })
+    });
+
+    // Transforms may have made values identical, so these are deduplicated.
+    values
+        .filter(|value| !value.is_empty())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// The name of a group, without the domain of its spn.
+fn group_name(spn: &str) -> String {
+    spn.split_once('@')
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| spn.to_string())
+}
+
 fn extra_claims_for_account(
+    entry: &EntrySealedCommitted,
     account: &Account,
 
     claim_map: &BTreeMap<Uuid, Vec<(String, ClaimValue)>>,
+    computed_claims: &[Oauth2ComputedClaim],
 
     scopes: &BTreeSet<String>,
 ) -> BTreeMap<String, serde_json::Value> {
@@ -2930,6 +4492,26 @@ fn extra_claims_for_account(
         extra_claims.insert(claim_name.to_string(), claim_value.to_json_value());
     }
 
+    // Computed claims don't replace a claim map of the same name, as the claim map is
+    // the more specific configuration for the members of that group.
+    for computed_claim in computed_claims {
+        let values = computed_claim_values(entry, account, computed_claim);
+        if values.is_empty() {
+            continue;
+        }
+
+        let value = match &computed_claim.join {
+            Some(separator) => serde_json::Value::String(values.join(separator)),
+            None => serde_json::Value::Array(
+                values.into_iter().map(serde_json::Value::String).collect(),
+            ),
+        };
+
+        if let BTreeEntry::Vacant(e) = extra_claims.entry(computed_claim.claim.clone()) {
+            e.insert(value);
+        }
+    }
+
     // Now perform our custom claim's from scopes. We do these second so that
     // a user can't stomp our claim names.
 
@@ -2944,9 +4526,35 @@ fn extra_claims_for_account(
         );
     }
 
-    if scopes.contains(OAUTH2_SCOPE_GROUPS) {
-        extra_claims.insert(
-            OAUTH2_SCOPE_GROUPS.to_string(),
+    // These scopes list each group once in the groups claim, as described by RFC 9068. If
+    // more than one is granted, the first of name, spn and uuid is used.
+    let groups = if scopes.contains(OAUTH2_SCOPE_GROUPS_NAME) {
+        Some(
+            account
+                .groups
+                .iter()
+                .map(|g| serde_json::Value::String(group_name(g.spn())))
+                .collect::<Vec<_>>(),
+        )
+    } else if scopes.contains(OAUTH2_SCOPE_GROUPS_SPN) {
+        Some(
+            account
+                .groups
+                .iter()
+                .map(|g| serde_json::Value::String(g.spn().clone()))
+                .collect(),
+        )
+    } else if scopes.contains(OAUTH2_SCOPE_GROUPS_UUID) {
+        Some(
+            account
+                .groups
+                .iter()
+                .map(|g| serde_json::Value::String(g.uuid().as_hyphenated().to_string()))
+                .collect(),
+        )
+    } else if scopes.contains(OAUTH2_SCOPE_GROUPS) {
+        // The original groups scope lists both the spn and uuid of each group.
+        Some(
             account
                 .groups
                 .iter()
@@ -2954,7 +4562,17 @@ fn extra_claims_for_account(
                     let proto_group = x.to_proto();
                     [proto_group.spn, proto_group.uuid]
                 })
+                .map(serde_json::Value::String)
                 .collect(),
+        )
+    } else {
+        None
+    };
+
+    if let Some(groups) = groups {
+        extra_claims.insert(
+            OAUTH2_SCOPE_GROUPS.to_string(),
+            serde_json::Value::Array(groups),
         );
     }
 
@@ -2963,6 +4581,46 @@ fn extra_claims_for_account(
     extra_claims
 }
 
+/// The thumbprint of a tls client certificate, used to bind tokens to it.
+/// ref <https://www.rfc-editor.org/rfc/rfc8705#section-3.1>
+fn client_cert_thumbprint(client_cert: &ClientCertInfo) -> Result<String, Oauth2Error> {
+    client_cert
+        .certificate
+        .to_der()
+        .map(|der| general_purpose::URL_SAFE_NO_PAD.encode(sha::sha256(&der)))
+        .map_err(|err| {
+            error!(?err, "Unable to encode client certificate");
+            Oauth2Error::ServerError(OperationError::InvalidState)
+        })
+}
+
+/// If a token bound by `bound` may be used by a client that presented `cnf`. Each key or
+/// certificate the token is bound to must have been presented.
+fn cnf_is_satisfied(
+    bound: Option<&OAuth2TokenConfirmation>,
+    cnf: Option<&OAuth2TokenConfirmation>,
+) -> bool {
+    let Some(bound) = bound else {
+        return true;
+    };
+
+    let presented_jkt = cnf.and_then(|cnf| cnf.jkt.as_ref());
+    let presented_x5t = cnf.and_then(|cnf| cnf.x5t_s256.as_ref());
+
+    (bound.jkt.is_none() || bound.jkt.as_ref() == presented_jkt)
+        && (bound.x5t_s256.is_none() || bound.x5t_s256.as_ref() == presented_x5t)
+}
+
+/// Only DPoP changes the type of the token. Certificate bound tokens remain bearer tokens.
+/// ref <https://www.rfc-editor.org/rfc/rfc8705#section-3>
+fn access_token_type(cnf: Option<&OAuth2TokenConfirmation>) -> AccessTokenType {
+    if cnf.is_some_and(|cnf| cnf.jkt.is_some()) {
+        AccessTokenType::DPoP
+    } else {
+        AccessTokenType::Bearer
+    }
+}
+
 fn validate_scopes(req_scopes: &BTreeSet<String>) -> Result<(), Oauth2Error> {
     let failed_scopes = req_scopes
         .iter()
@@ -3046,6 +4704,25 @@ fn check_is_loopback(redirect_uri: &Url) -> bool {
     })
 }
 
+/// Check that a redirect uri may be registered by dynamic client registration. This follows
+/// the policy of [`Oauth2RS::check_redirect_uri`], so that a client can't register a redirect
+/// that it would not be permitted to use. Web applications must use https to a remote host.
+/// Only native applications may use a loopback uri or a private-use scheme.
+fn check_registration_redirect_uri(redirect_uri: &Url, native: bool) -> bool {
+    // https://www.rfc-editor.org/rfc/rfc6749#section-3.1.2
+    // Must not include a fragment.
+    if redirect_uri.fragment().is_some() || redirect_uri.cannot_be_a_base() {
+        return false;
+    }
+
+    match redirect_uri.scheme() {
+        "https" => native || !check_is_loopback(redirect_uri),
+        "http" => native && check_is_loopback(redirect_uri),
+        // ref <https://www.rfc-editor.org/rfc/rfc8252#section-7.1>
+        scheme => native && scheme.contains('.'),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use base64::{engine::general_purpose, Engine as _};
@@ -3057,7 +4734,8 @@ mod tests {
 
     use compact_jwt::{
         compact::JwkUse, crypto::JwsRs256Verifier, dangernoverify::JwsDangerReleaseWithoutVerify,
-        JwaAlg, Jwk, JwsCompact, JwsEs256Verifier, JwsVerifier, OidcSubject, OidcUnverified,
+        JwaAlg, Jwk, JwsCompact, JwsEs256Signer, JwsEs256Verifier, JwsVerifier, OidcSubject,
+        OidcUnverified,
     };
     use kanidm_proto::constants::*;
     use kanidm_proto::internal::{SshPublicKey, UserAuthToken};
@@ -3065,10 +4743,15 @@ mod tests {
     use openssl::sha;
 
     use crate::idm::accountpolicy::ResolvedAccountPolicy;
-    use crate::idm::oauth2::{host_is_local, AuthoriseResponse, Oauth2Error, OauthRSType};
+    use crate::idm::audit::AuditEvent;
+    use crate::idm::dpop::{self, DPoPProof};
+    use crate::idm::oauth2::{
+        client_cert_thumbprint, host_is_local, AuthoriseReject, AuthoriseResponse,
+        ListOauth2SessionEvent, Oauth2Error, OauthRSType,
+    };
     use crate::idm::server::{IdmServer, IdmServerTransaction};
     use crate::prelude::*;
-    use crate::value::{AuthType, OauthClaimMapJoin, SessionState};
+    use crate::value::{AuthType, Oauth2SessionRevokeReason, OauthClaimMapJoin, SessionState};
     use crate::valueset::{ValueSetOauthScopeMap, ValueSetSshKey};
 
     use crate::credential::Credential;
@@ -3684,7 +5367,7 @@ mod tests {
             idms_prox_read
                 .check_oauth2_authorisation(Some(&ident), &auth_req, ct)
                 .unwrap_err()
-                == Oauth2Error::InvalidOrigin
+                == Oauth2Error::RedirectUriRejected(OperationError::OA0001RedirectUriNotRegistered)
         );
 
         // * invalid uri in the redirect
@@ -3706,7 +5389,7 @@ mod tests {
             idms_prox_read
                 .check_oauth2_authorisation(Some(&ident), &auth_req, ct)
                 .unwrap_err()
-                == Oauth2Error::InvalidOrigin
+                == Oauth2Error::RedirectUriRejected(OperationError::OA0001RedirectUriNotRegistered)
         );
 
         // * invalid uri (doesn't match query params)
@@ -3728,7 +5411,7 @@ mod tests {
             idms_prox_read
                 .check_oauth2_authorisation(Some(&ident), &auth_req, ct)
                 .unwrap_err()
-                == Oauth2Error::InvalidOrigin
+                == Oauth2Error::RedirectUriRejected(OperationError::OA0001RedirectUriNotRegistered)
         );
 
         let auth_req = AuthorisationRequest {
@@ -3749,7 +5432,7 @@ mod tests {
             idms_prox_read
                 .check_oauth2_authorisation(Some(&ident), &auth_req, ct)
                 .unwrap_err()
-                == Oauth2Error::InvalidOrigin
+                == Oauth2Error::RedirectUriRejected(OperationError::OA0001RedirectUriNotRegistered)
         );
 
         let auth_req = AuthorisationRequest {
@@ -3770,7 +5453,7 @@ mod tests {
             idms_prox_read
                 .check_oauth2_authorisation(Some(&ident), &auth_req, ct)
                 .unwrap_err()
-                == Oauth2Error::InvalidOrigin
+                == Oauth2Error::RedirectUriRejected(OperationError::OA0001RedirectUriNotRegistered)
         );
 
         // Not Authenticated
@@ -4837,6 +6520,14 @@ mod tests {
         assert!(discovery
             .introspection_endpoint_auth_signing_alg_values_supported
             .is_none());
+        assert_eq!(
+            discovery.dpop_signing_alg_values_supported,
+            Some(vec![IdTokenSignAlg::ES256])
+        );
+        assert_eq!(
+            discovery.tls_client_certificate_bound_access_tokens,
+            Some(true)
+        );
 
         assert_eq!(
             discovery.code_challenge_methods_supported,
@@ -5137,7 +6828,7 @@ mod tests {
         assert_eq!(oidc.nbf, Some(iat));
         // Previously this was the auth session but it's now inline with the access token expiry.
         assert_eq!(oidc.exp, iat + (OAUTH2_ACCESS_TOKEN_EXPIRY as i64));
-        assert!(oidc.auth_time.is_none());
+        assert_eq!(oidc.auth_time, Some(iat));
         // Is nonce correctly passed through?
         assert_eq!(oidc.nonce, Some("abcdef".to_string()));
         assert!(oidc.at_hash.is_none());
@@ -5157,7 +6848,7 @@ mod tests {
         // Does our access token work with the userinfo endpoint?
         // Do the id_token details line up to the userinfo?
         let userinfo = idms_prox_read
-            .oauth2_openid_userinfo("test_resource_server", access_token, ct)
+            .oauth2_openid_userinfo("test_resource_server", access_token, None, None, ct)
             .expect("failed to get userinfo");
 
         assert_eq!(oidc.iss, userinfo.iss);
@@ -5202,7 +6893,7 @@ mod tests {
         let mut idms_prox_read = idms.proxy_read().await.unwrap();
 
         let userinfo = idms_prox_read
-            .oauth2_openid_userinfo("test_resource_server", access_token, ct)
+            .oauth2_openid_userinfo("test_resource_server", access_token, None, None, ct)
             .expect("failed to get userinfo");
 
         assert_eq!(oidc.iss, userinfo.iss);
@@ -5304,7 +6995,7 @@ mod tests {
         );
         // Do the id_token details line up to the userinfo?
         let userinfo = idms_prox_read
-            .oauth2_openid_userinfo("test_resource_server", access_token, ct)
+            .oauth2_openid_userinfo("test_resource_server", access_token, None, None, ct)
             .expect("failed to get userinfo");
 
         assert_eq!(oidc.s_claims, userinfo.s_claims);
@@ -5398,7 +7089,7 @@ mod tests {
 
         // Do the id_token details line up to the userinfo?
         let userinfo = idms_prox_read
-            .oauth2_openid_userinfo("test_resource_server", access_token, ct)
+            .oauth2_openid_userinfo("test_resource_server", access_token, None, None, ct)
             .expect("failed to get userinfo");
 
         // does the userinfo endpoint provide the same groups?
@@ -5406,45 +7097,32 @@ mod tests {
     }
 
     #[idm_test]
-    async fn test_idm_oauth2_openid_ssh_publickey_claim(
+    async fn test_idm_oauth2_openid_group_name_claims(
         idms: &IdmServer,
         _idms_delayed: &mut IdmServerDelayed,
     ) {
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
-        let (secret, _uat, ident, client_uuid) =
-            setup_oauth2_resource_server_basic(idms, ct, true, false, true).await;
-
-        // Extra setup for our test - add the correct claim and give an ssh publickey
-        // to our testperson
-        const ECDSA_SSH_PUBLIC_KEY: &str = "ecdsa-sha2-nistp521 AAAAE2VjZHNhLXNoYTItbmlzdHA1MjEAAAAIbmlzdHA1MjEAAACFBAGyIY7o3BtOzRiJ9vvjj96bRImwmyy5GvFSIUPlK00HitiAWGhiO1jGZKmK7220Oe4rqU3uAwA00a0758UODs+0OQHLMDRtl81lzPrVSdrYEDldxH9+a86dBZhdm0e15+ODDts2LHUknsJCRRldO4o9R9VrohlF7cbyBlnhJQrR4S+Oag== william@amethyst";
-        let ssh_pubkey = SshPublicKey::from_string(ECDSA_SSH_PUBLIC_KEY).unwrap();
-
-        let scope_set = BTreeSet::from([OAUTH2_SCOPE_SSH_PUBLICKEYS.to_string()]);
+        let (secret, _uat, ident, oauth2_rs_uuid) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, false).await;
 
         let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
 
-        idms_prox_write
+        let modlist = ModifyList::new_list(vec![Modify::Present(
+            Attribute::OAuth2RsScopeMap,
+            Value::new_oauthscopemap(
+                UUID_IDM_ALL_PERSONS,
+                btreeset![OAUTH2_SCOPE_GROUPS_NAME.to_string()],
+            )
+            .expect("invalid oauthscope"),
+        )]);
+
+        assert!(idms_prox_write
             .qs_write
-            .internal_batch_modify(
-                [
-                    (
-                        UUID_TESTPERSON_1,
-                        ModifyList::new_set(
-                            Attribute::SshPublicKey,
-                            ValueSetSshKey::new("label".to_string(), ssh_pubkey),
-                        ),
-                    ),
-                    (
-                        client_uuid,
-                        ModifyList::new_set(
-                            Attribute::OAuth2RsSupScopeMap,
-                            ValueSetOauthScopeMap::new(UUID_IDM_ALL_ACCOUNTS, scope_set),
-                        ),
-                    ),
-                ]
-                .into_iter(),
+            .internal_modify(
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(oauth2_rs_uuid))),
+                &modlist,
             )
-            .expect("Failed to modify test entries");
+            .is_ok());
 
         assert!(idms_prox_write.commit().is_ok());
 
@@ -5459,7 +7137,132 @@ mod tests {
             &ident,
             ct,
             code_challenge,
-            "openid groups".to_string()
+            "openid groups_name".to_string()
+        );
+
+        let AuthoriseResponse::ConsentRequested { consent_token, .. } = consent_request else {
+            unreachable!();
+        };
+
+        drop(idms_prox_read);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let permit_success = idms_prox_write
+            .check_oauth2_authorise_permit(&ident, &consent_token, ct)
+            .expect("Failed to perform OAuth2 permit");
+
+        let token_req: AccessTokenRequest = GrantTypeReq::AuthorizationCode {
+            code: permit_success.code,
+            redirect_uri: Url::parse("https://demo.example.com/oauth2/result").unwrap(),
+            code_verifier,
+        }
+        .into();
+
+        let token_response = idms_prox_write
+            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+            .expect("Failed to perform OAuth2 token exchange");
+
+        let id_token = token_response.id_token.expect("No id_token in response!");
+        let access_token =
+            JwsCompact::from_str(&token_response.access_token).expect("Invalid Access Token");
+
+        assert!(idms_prox_write.commit().is_ok());
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let mut jwkset = idms_prox_read
+            .oauth2_openid_publickey("test_resource_server")
+            .expect("Failed to get public key");
+        let public_jwk = jwkset.keys.pop().expect("no such jwk");
+
+        let jws_validator =
+            JwsEs256Verifier::try_from(&public_jwk).expect("failed to build validator");
+
+        let oidc_unverified =
+            OidcUnverified::from_str(&id_token).expect("Failed to parse id_token");
+
+        let oidc = jws_validator
+            .verify(&oidc_unverified)
+            .unwrap()
+            .verify_exp(ct.as_secs() as i64)
+            .expect("Failed to verify oidc");
+
+        // Only the plain group names are present, without the domain or uuids.
+        let groups = oidc
+            .claims
+            .get("groups")
+            .and_then(|v| v.as_array())
+            .expect("No groups claim");
+
+        assert!(groups.contains(&serde_json::json!("testgroup")));
+        assert!(groups.contains(&serde_json::json!("idm_all_persons")));
+        assert!(!groups.contains(&serde_json::json!(STR_UUID_IDM_ALL_ACCOUNTS)));
+        assert!(groups
+            .iter()
+            .filter_map(|v| v.as_str())
+            .all(|group| !group.contains('@')));
+
+        let userinfo = idms_prox_read
+            .oauth2_openid_userinfo("test_resource_server", access_token, None, None, ct)
+            .expect("failed to get userinfo");
+
+        assert_eq!(oidc.claims.get("groups"), userinfo.claims.get("groups"));
+    }
+
+    #[idm_test]
+    async fn test_idm_oauth2_openid_ssh_publickey_claim(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (secret, _uat, ident, client_uuid) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, true).await;
+
+        // Extra setup for our test - add the correct claim and give an ssh publickey
+        // to our testperson
+        const ECDSA_SSH_PUBLIC_KEY: &str = "ecdsa-sha2-nistp521 AAAAE2VjZHNhLXNoYTItbmlzdHA1MjEAAAAIbmlzdHA1MjEAAACFBAGyIY7o3BtOzRiJ9vvjj96bRImwmyy5GvFSIUPlK00HitiAWGhiO1jGZKmK7220Oe4rqU3uAwA00a0758UODs+0OQHLMDRtl81lzPrVSdrYEDldxH9+a86dBZhdm0e15+ODDts2LHUknsJCRRldO4o9R9VrohlF7cbyBlnhJQrR4S+Oag== william@amethyst";
+        let ssh_pubkey = SshPublicKey::from_string(ECDSA_SSH_PUBLIC_KEY).unwrap();
+
+        let scope_set = BTreeSet::from([OAUTH2_SCOPE_SSH_PUBLICKEYS.to_string()]);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        idms_prox_write
+            .qs_write
+            .internal_batch_modify(
+                [
+                    (
+                        UUID_TESTPERSON_1,
+                        ModifyList::new_set(
+                            Attribute::SshPublicKey,
+                            ValueSetSshKey::new("label".to_string(), ssh_pubkey),
+                        ),
+                    ),
+                    (
+                        client_uuid,
+                        ModifyList::new_set(
+                            Attribute::OAuth2RsSupScopeMap,
+                            ValueSetOauthScopeMap::new(UUID_IDM_ALL_ACCOUNTS, scope_set),
+                        ),
+                    ),
+                ]
+                .into_iter(),
+            )
+            .expect("Failed to modify test entries");
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let client_authz = ClientAuthInfo::encode_basic("test_resource_server", secret.as_str());
+
+        let idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let (code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+
+        let consent_request = good_authorisation_request!(
+            idms_prox_read,
+            &ident,
+            ct,
+            code_challenge,
+            "openid groups".to_string()
         );
 
         let AuthoriseResponse::ConsentRequested { consent_token, .. } = consent_request else {
@@ -5526,7 +7329,7 @@ mod tests {
 
         // Do the id_token details line up to the userinfo?
         let userinfo = idms_prox_read
-            .oauth2_openid_userinfo("test_resource_server", access_token, ct)
+            .oauth2_openid_userinfo("test_resource_server", access_token, None, None, ct)
             .expect("failed to get userinfo");
 
         // does the userinfo endpoint provide the same groups?
@@ -5704,6 +7507,130 @@ mod tests {
         assert!(idms_prox_write.commit().is_ok());
     }
 
+    #[idm_test]
+    async fn test_idm_oauth2_openid_key_object_signing(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (secret, _uat, ident, rs_uuid) =
+            setup_oauth2_resource_server_basic(idms, ct, false, false, false).await;
+
+        // Move the client to rs256 signing from its own key object.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                rs_uuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::OAuth2JwtSignAlg,
+                    Value::new_iutf8("rs256"),
+                ),
+            )
+            .expect("Unable to set signing algorithm");
+        assert!(idms_prox_write.commit().is_ok());
+
+        let idms_prox_read = idms.proxy_read().await.unwrap();
+        let discovery = idms_prox_read
+            .oauth2_openid_discovery("test_resource_server")
+            .expect("Failed to get discovery");
+        assert_eq!(
+            discovery.id_token_signing_alg_values_supported,
+            vec![IdTokenSignAlg::RS256]
+        );
+
+        // The previous es256 key is retained so existing tokens remain valid.
+        let jwkset = idms_prox_read
+            .oauth2_openid_publickey("test_resource_server")
+            .expect("Failed to get public key");
+        assert!(jwkset.keys.iter().any(|jwk| matches!(jwk, Jwk::EC { .. })));
+        let rsa_keys: Vec<_> = jwkset
+            .keys
+            .iter()
+            .filter(|jwk| matches!(jwk, Jwk::RSA { .. }))
+            .cloned()
+            .collect();
+        assert_eq!(rsa_keys.len(), 1);
+
+        let (code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+
+        let consent_request = good_authorisation_request!(
+            idms_prox_read,
+            &ident,
+            ct,
+            code_challenge,
+            OAUTH2_SCOPE_OPENID.to_string()
+        );
+
+        let AuthoriseResponse::ConsentRequested { consent_token, .. } = consent_request else {
+            unreachable!();
+        };
+
+        drop(idms_prox_read);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let permit_success = idms_prox_write
+            .check_oauth2_authorise_permit(&ident, &consent_token, ct)
+            .expect("Failed to perform OAuth2 permit");
+
+        let token_req = AccessTokenRequest {
+            grant_type: GrantTypeReq::AuthorizationCode {
+                code: permit_success.code,
+                redirect_uri: Url::parse("https://demo.example.com/oauth2/result").unwrap(),
+                code_verifier,
+            },
+            client_id: Some("test_resource_server".to_string()),
+            client_secret: Some(secret),
+        };
+
+        let token_response = idms_prox_write
+            .check_oauth2_token_exchange(&ClientAuthInfo::none(), &token_req, ct)
+            .expect("Failed to perform OAuth2 token exchange");
+
+        let id_token = token_response.id_token.expect("No id_token in response!");
+
+        let jws_validator =
+            JwsRs256Verifier::try_from(&rsa_keys[0]).expect("failed to build validator");
+
+        let oidc_unverified =
+            OidcUnverified::from_str(&id_token).expect("Failed to parse id_token");
+
+        let oidc = jws_validator
+            .verify(&oidc_unverified)
+            .unwrap()
+            .verify_exp(ct.as_secs() as i64)
+            .expect("Failed to verify oidc");
+
+        assert_eq!(oidc.sub, OidcSubject::U(UUID_TESTPERSON_1));
+
+        // Rotating the keys of the client publishes the new key alongside the old one.
+        let ct_future = ct + Duration::from_secs(300);
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                rs_uuid,
+                &ModifyList::new_append(
+                    Attribute::KeyActionRotate,
+                    Value::new_datetime_epoch(ct_future),
+                ),
+            )
+            .expect("Unable to rotate key");
+        assert!(idms_prox_write.commit().is_ok());
+
+        let idms_prox_read = idms.proxy_read().await.unwrap();
+        let jwkset = idms_prox_read
+            .oauth2_openid_publickey("test_resource_server")
+            .expect("Failed to get public key");
+        assert_eq!(
+            jwkset
+                .keys
+                .iter()
+                .filter(|jwk| matches!(jwk, Jwk::RSA { .. }))
+                .count(),
+            2
+        );
+    }
+
     #[idm_test]
     async fn test_idm_oauth2_consent_granted_and_changed_workflow(
         idms: &IdmServer,
@@ -6097,7 +8024,7 @@ mod tests {
             idms_prox_read
                 .check_oauth2_authorisation(Some(&ident), &auth_req, ct)
                 .unwrap_err()
-                == Oauth2Error::InvalidOrigin
+                == Oauth2Error::RedirectUriRejected(OperationError::OA0001RedirectUriNotRegistered)
         );
 
         // This does have https
@@ -6332,49 +8259,345 @@ mod tests {
         assert!(idms_prox_write.commit().is_ok());
     }
 
-    // refresh when OAuth2 parent session exp / missing.
     #[idm_test]
-    async fn test_idm_oauth2_refresh_token_oauth2_session_expired(
+    async fn test_idm_oauth2_dpop_bound_tokens(
         idms: &IdmServer,
-        idms_delayed: &mut IdmServerDelayed,
+        _idms_delayed: &mut IdmServerDelayed,
     ) {
-        // First, setup to get a token.
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (secret, _uat, ident, _) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, false).await;
+        let client_authz = ClientAuthInfo::encode_basic("test_resource_server", secret.as_str());
 
-        let (access_token_response_1, client_authz) =
-            setup_refresh_token(idms, idms_delayed, ct).await;
+        let signer = JwsEs256Signer::generate_es256()
+            .expect("Unable to create signer")
+            .set_sign_option_embed_jwk(true);
+        let token_url = format!("https://idm.example.com{}", uri::OAUTH2_TOKEN_ENDPOINT);
+        let userinfo_path = "/oauth2/openid/test_resource_server/userinfo";
+        let userinfo_url = format!("https://idm.example.com{}", userinfo_path);
 
-        // ============================================
-        // Revoke the OAuth2 session
+        let idms_prox_read = idms.proxy_read().await.unwrap();
 
-        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
-        let revoke_request = TokenRevokeRequest {
-            token: access_token_response_1.access_token.clone(),
-            token_type_hint: None,
+        let (code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+        let consent_request = good_authorisation_request!(
+            idms_prox_read,
+            &ident,
+            ct,
+            code_challenge,
+            OAUTH2_SCOPE_OPENID.to_string()
+        );
+
+        let AuthoriseResponse::ConsentRequested { consent_token, .. } = consent_request else {
+            unreachable!();
         };
-        assert!(idms_prox_write
-            .oauth2_token_revoke(&client_authz, &revoke_request, ct,)
-            .is_ok());
-        assert!(idms_prox_write.commit().is_ok());
 
-        // ============================================
-        // then attempt a refresh.
+        drop(idms_prox_read);
         let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
 
-        let refresh_token = access_token_response_1
-            .refresh_token
-            .as_ref()
-            .expect("no refresh token was issued")
-            .clone();
+        let permit_success = idms_prox_write
+            .check_oauth2_authorise_permit(&ident, &consent_token, ct)
+            .expect("Failed to perform OAuth2 permit");
 
-        let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
-            refresh_token,
-            scope: None,
-        }
-        .into();
-        let access_token_response_2 = idms_prox_write
-            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
-            // Should be unable to exchange.
+        // Exchange the code with a proof, binding the tokens to our key.
+        let (proof, jkt) = dpop::test_dpop_proof(&signer, "POST", &token_url, None, ct);
+        let mut dpop_client_authz = client_authz.clone();
+        dpop_client_authz.dpop = Some(DPoPProof {
+            proof,
+            method: "POST".to_string(),
+            path: uri::OAUTH2_TOKEN_ENDPOINT.to_string(),
+        });
+
+        let token_req: AccessTokenRequest = GrantTypeReq::AuthorizationCode {
+            code: permit_success.code,
+            redirect_uri: Url::parse("https://demo.example.com/oauth2/result").unwrap(),
+            code_verifier,
+        }
+        .into();
+        let access_token_response = idms_prox_write
+            .check_oauth2_token_exchange(&dpop_client_authz, &token_req, ct)
+            .expect("Unable to exchange for OAuth2 token");
+
+        assert_eq!(access_token_response.token_type, AccessTokenType::DPoP);
+
+        // A bound refresh token can't be used without a proof from the same key.
+        let refresh_token = access_token_response
+            .refresh_token
+            .clone()
+            .expect("no refresh token was issued");
+        let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
+            refresh_token,
+            scope: None,
+        }
+        .into();
+
+        let refresh_err = idms_prox_write
+            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+            .unwrap_err();
+        assert_eq!(refresh_err, Oauth2Error::InvalidDpopProof);
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        // Introspection shows the key the token is bound to.
+        let intr_request = AccessTokenIntrospectRequest {
+            token: access_token_response.access_token.clone(),
+            token_type_hint: None,
+        };
+        let intr_response = idms_prox_read
+            .check_oauth2_token_introspect(&client_authz, &intr_request, ct)
+            .expect("Failed to inspect token");
+
+        assert!(intr_response.active);
+        assert_eq!(intr_response.token_type, Some(AccessTokenType::DPoP));
+        assert_eq!(intr_response.cnf.and_then(|cnf| cnf.jkt), Some(jkt));
+
+        // The userinfo endpoint requires a proof for the token from the same key.
+        let access_token = JwsCompact::from_str(&access_token_response.access_token)
+            .expect("Invalid access token");
+
+        let userinfo_err = idms_prox_read
+            .oauth2_openid_userinfo("test_resource_server", access_token.clone(), None, None, ct)
+            .unwrap_err();
+        assert_eq!(userinfo_err, Oauth2Error::InvalidToken);
+
+        let other_signer = JwsEs256Signer::generate_es256()
+            .expect("Unable to create signer")
+            .set_sign_option_embed_jwk(true);
+        let (proof, _) = dpop::test_dpop_proof(
+            &other_signer,
+            "GET",
+            &userinfo_url,
+            Some(&access_token_response.access_token),
+            ct,
+        );
+        let other_dpop = DPoPProof {
+            proof,
+            method: "GET".to_string(),
+            path: userinfo_path.to_string(),
+        };
+
+        let userinfo_err = idms_prox_read
+            .oauth2_openid_userinfo(
+                "test_resource_server",
+                access_token.clone(),
+                Some(&other_dpop),
+                None,
+                ct,
+            )
+            .unwrap_err();
+        assert_eq!(userinfo_err, Oauth2Error::InvalidDpopProof);
+
+        let (proof, _) = dpop::test_dpop_proof(
+            &signer,
+            "GET",
+            &userinfo_url,
+            Some(&access_token_response.access_token),
+            ct,
+        );
+        let dpop = DPoPProof {
+            proof,
+            method: "GET".to_string(),
+            path: userinfo_path.to_string(),
+        };
+
+        let userinfo = idms_prox_read
+            .oauth2_openid_userinfo("test_resource_server", access_token, Some(&dpop), None, ct)
+            .expect("Failed to get userinfo");
+        assert_eq!(userinfo.aud, "test_resource_server");
+    }
+
+    fn test_client_cert() -> ClientCertInfo {
+        use kanidm_lib_crypto::mtls::build_self_signed_server_and_client_identity;
+        use kanidm_lib_crypto::x509_cert::{der::Decode, x509_public_key_s256, Certificate};
+
+        let (_, x509) =
+            build_self_signed_server_and_client_identity(Uuid::new_v4(), "client.example.com", 1)
+                .expect("Unable to create client certificate");
+        let certificate = x509
+            .to_der()
+            .ok()
+            .and_then(|der| Certificate::from_der(&der).ok())
+            .expect("Invalid client certificate");
+        let public_key_s256 =
+            x509_public_key_s256(&certificate).expect("Unable to digest public key");
+
+        ClientCertInfo {
+            public_key_s256,
+            certificate,
+        }
+    }
+
+    #[idm_test]
+    async fn test_idm_oauth2_mtls_bound_tokens(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (secret, _uat, ident, _) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, false).await;
+        let client_authz = ClientAuthInfo::encode_basic("test_resource_server", secret.as_str());
+
+        let client_cert = test_client_cert();
+        let mut mtls_client_authz = client_authz.clone();
+        mtls_client_authz.client_cert = Some(client_cert.clone());
+
+        let idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let (code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+        let consent_request = good_authorisation_request!(
+            idms_prox_read,
+            &ident,
+            ct,
+            code_challenge,
+            OAUTH2_SCOPE_OPENID.to_string()
+        );
+
+        let AuthoriseResponse::ConsentRequested { consent_token, .. } = consent_request else {
+            unreachable!();
+        };
+
+        drop(idms_prox_read);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let permit_success = idms_prox_write
+            .check_oauth2_authorise_permit(&ident, &consent_token, ct)
+            .expect("Failed to perform OAuth2 permit");
+
+        // Exchange the code over mtls, binding the access token to our certificate.
+        let token_req: AccessTokenRequest = GrantTypeReq::AuthorizationCode {
+            code: permit_success.code,
+            redirect_uri: Url::parse("https://demo.example.com/oauth2/result").unwrap(),
+            code_verifier,
+        }
+        .into();
+        let access_token_response = idms_prox_write
+            .check_oauth2_token_exchange(&mtls_client_authz, &token_req, ct)
+            .expect("Unable to exchange for OAuth2 token");
+
+        // Certificate bound tokens are still bearer tokens.
+        assert_eq!(access_token_response.token_type, AccessTokenType::Bearer);
+
+        // The refresh token is not bound to the certificate, so the client may renew it.
+        let refresh_token = access_token_response
+            .refresh_token
+            .clone()
+            .expect("no refresh token was issued");
+        let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
+            refresh_token,
+            scope: None,
+        }
+        .into();
+
+        let refresh_response = idms_prox_write
+            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+            .expect("Unable to refresh OAuth2 token");
+        assert_eq!(refresh_response.token_type, AccessTokenType::Bearer);
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        // Introspection shows the certificate the token is bound to.
+        let intr_request = AccessTokenIntrospectRequest {
+            token: access_token_response.access_token.clone(),
+            token_type_hint: None,
+        };
+        let intr_response = idms_prox_read
+            .check_oauth2_token_introspect(&client_authz, &intr_request, ct)
+            .expect("Failed to inspect token");
+
+        assert!(intr_response.active);
+        let cnf = intr_response.cnf.expect("Token is not bound");
+        assert_eq!(cnf.jkt, None);
+        assert_eq!(
+            cnf.x5t_s256,
+            Some(client_cert_thumbprint(&client_cert).expect("Unable to create thumbprint"))
+        );
+
+        // The userinfo endpoint requires the same certificate.
+        let access_token = JwsCompact::from_str(&access_token_response.access_token)
+            .expect("Invalid access token");
+
+        let userinfo_err = idms_prox_read
+            .oauth2_openid_userinfo("test_resource_server", access_token.clone(), None, None, ct)
+            .unwrap_err();
+        assert_eq!(userinfo_err, Oauth2Error::InvalidToken);
+
+        let other_cert = test_client_cert();
+        let userinfo_err = idms_prox_read
+            .oauth2_openid_userinfo(
+                "test_resource_server",
+                access_token.clone(),
+                None,
+                Some(&other_cert),
+                ct,
+            )
+            .unwrap_err();
+        assert_eq!(userinfo_err, Oauth2Error::InvalidToken);
+
+        let userinfo = idms_prox_read
+            .oauth2_openid_userinfo(
+                "test_resource_server",
+                access_token,
+                None,
+                Some(&client_cert),
+                ct,
+            )
+            .expect("Failed to get userinfo");
+        assert_eq!(userinfo.aud, "test_resource_server");
+
+        // The refreshed token was issued without a certificate, so it is not bound.
+        let refreshed_token =
+            JwsCompact::from_str(&refresh_response.access_token).expect("Invalid access token");
+        assert!(idms_prox_read
+            .oauth2_openid_userinfo("test_resource_server", refreshed_token, None, None, ct)
+            .is_ok());
+    }
+
+    // refresh when OAuth2 parent session exp / missing.
+    #[idm_test]
+    async fn test_idm_oauth2_refresh_token_oauth2_session_expired(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        // First, setup to get a token.
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let (access_token_response_1, client_authz) =
+            setup_refresh_token(idms, idms_delayed, ct).await;
+
+        // ============================================
+        // Revoke the OAuth2 session
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let revoke_request = TokenRevokeRequest {
+            token: access_token_response_1.access_token.clone(),
+            token_type_hint: None,
+        };
+        assert!(idms_prox_write
+            .oauth2_token_revoke(&client_authz, &revoke_request, ct,)
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        // ============================================
+        // then attempt a refresh.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let refresh_token = access_token_response_1
+            .refresh_token
+            .as_ref()
+            .expect("no refresh token was issued")
+            .clone();
+
+        let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
+            refresh_token,
+            scope: None,
+        }
+        .into();
+        let access_token_response_2 = idms_prox_write
+            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+            // Should be unable to exchange.
             .unwrap_err();
 
         assert_eq!(access_token_response_2, Oauth2Error::InvalidGrant);
@@ -6521,39 +8744,50 @@ mod tests {
             .qs_write
             .internal_search_uuid(UUID_TESTPERSON_1)
             .expect("failed");
-        let valid = entry
+        let session = entry
             .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
             .and_then(|sessions| sessions.first_key_value())
-            .map(|(_, session)| !matches!(session.state, SessionState::RevokedAt(_)))
+            .map(|(_, session)| session.clone())
             // If there is no map, then something is wrong.
             .unwrap();
-        // The session should be invalid at this point.
-        assert!(!valid);
+        // The session should be invalid at this point, and show why it was revoked.
+        assert!(matches!(session.state, SessionState::RevokedAt(_)));
+        assert_eq!(
+            session.revoke_reason,
+            Some(Oauth2SessionRevokeReason::RefreshTokenReuse)
+        );
 
         assert!(idms_prox_write.commit().is_ok());
     }
 
-    // Test session divergence. This means that we have to:
-    // access + refresh 1
-    // use refresh 1 -> access + refresh 2 // don't commit this txn.
-    // use refresh 2 -> access + refresh 3
-    //    check the session state.
-
+    // Test that a client which has opted out of rotation may use a refresh token again without
+    // terminating the session, and that it is issued a refresh token with a later expiry.
     #[idm_test]
-    async fn test_idm_oauth2_refresh_token_divergence(
+    async fn test_idm_oauth2_refresh_token_reuse_allowed(
         idms: &IdmServer,
         idms_delayed: &mut IdmServerDelayed,
     ) {
-        // First, setup to get a token.
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
 
         let (access_token_response_1, client_authz) =
             setup_refresh_token(idms, idms_delayed, ct).await;
 
-        // ============================================
-        // Use the refresh token once
-        let ct = Duration::from_secs(TEST_CURRENT_TIME + 1);
         let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let rs_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid("test_resource_server")
+            .expect("Unable to resolve resource server");
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                rs_uuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::OAuth2AllowInsecureRefreshTokenReuse,
+                    Value::new_bool(true)
+                ),
+            )
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
 
         let refresh_token = access_token_response_1
             .refresh_token
@@ -6561,75 +8795,233 @@ mod tests {
             .expect("no refresh token was issued")
             .clone();
 
-        let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
-            refresh_token,
-            scope: None,
-        }
-        .into();
-
-        let access_token_response_2 = idms_prox_write
-            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
-            .expect("Unable to exchange for OAuth2 token");
+        let refresh_exp = |idms_prox_write: &IdmServerProxyWriteTransaction<'_>, token: &str| {
+            let data = idms_prox_write
+                .oauth2rs
+                .inner
+                .rs_set
+                .get("test_resource_server")
+                .expect("Client not found")
+                .token_fernet
+                .decrypt(token)
+                .expect("Unable to decrypt refresh token");
+            match serde_json::from_slice(&data).expect("Invalid refresh token") {
+                Oauth2TokenType::Refresh { exp, .. } => exp,
+                _ => unreachable!(),
+            }
+        };
 
-        // DO NOT COMMIT HERE - this is what forces the session issued_at
-        // time to stay at the original time!
-        drop(idms_prox_write);
+        for i in 1..=2 {
+            let ct = Duration::from_secs(TEST_CURRENT_TIME + i);
+            let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
 
-        // ============================================
-        let ct = Duration::from_secs(TEST_CURRENT_TIME + 2);
-        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+            let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
+                refresh_token: refresh_token.clone(),
+                scope: None,
+            }
+            .into();
 
-        let refresh_token = access_token_response_2
-            .refresh_token
-            .as_ref()
-            .expect("no refresh token was issued")
-            .clone();
+            let access_token_response = idms_prox_write
+                .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+                .expect("Unable to exchange for OAuth2 token");
+
+            let reissued = access_token_response
+                .refresh_token
+                .as_ref()
+                .expect("no refresh token was issued");
+            assert_eq!(
+                refresh_exp(&idms_prox_write, reissued),
+                refresh_exp(&idms_prox_write, &refresh_token) + i as i64
+            );
 
-        let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
-            refresh_token,
-            scope: None,
+            assert!(idms_prox_write.commit().is_ok());
         }
-        .into();
-
-        let _access_token_response_3 = idms_prox_write
-            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
-            .expect("Unable to exchange for OAuth2 token");
-
-        assert!(idms_prox_write.commit().is_ok());
-
-        // Success!
     }
 
+    // Test that reuse of a refresh token revokes every session under the same parent session,
+    // including sessions that were created by exchanging its access tokens.
     #[idm_test]
-    async fn test_idm_oauth2_refresh_token_scope_constraints(
+    async fn test_idm_oauth2_refresh_token_reuse_revokes_family(
         idms: &IdmServer,
         idms_delayed: &mut IdmServerDelayed,
     ) {
-        // First, setup to get a token.
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
 
         let (access_token_response_1, client_authz) =
             setup_refresh_token(idms, idms_delayed, ct).await;
 
-        // https://www.rfc-editor.org/rfc/rfc6749#section-1.5
-        // Refresh tokens are issued to the client by the authorization
-        // server and are used to obtain a new access token when the
-        // current access token becomes invalid or expires, or to obtain
-        // additional access tokens with identical or narrower scope
-        // (access tokens may have a shorter lifetime and fewer
-        // permissions than authorized by the resource owner).
-
-        let ct = Duration::from_secs(TEST_CURRENT_TIME + 10);
-        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
-
         let refresh_token = access_token_response_1
             .refresh_token
             .as_ref()
             .expect("no refresh token was issued")
             .clone();
 
-        // Get the initial scopes.
-        let jws_verifier = JwsDangerReleaseWithoutVerify::default();
+        // Exchange the access token, creating a second session under the same parent.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let token_req = AccessTokenRequest::from(GrantTypeReq::TokenExchange {
+            subject_token: access_token_response_1.access_token.clone(),
+            subject_token_type: OAUTH2_TOKEN_TYPE_ACCESS_TOKEN.to_string(),
+            actor_token: None,
+            actor_token_type: None,
+            requested_token_type: None,
+            audience: None,
+            scope: Some(btreeset!["supplement".to_string()]),
+        });
+        let exchanged = idms_prox_write
+            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+            .expect("Failed to exchange token");
+        assert!(idms_prox_write.commit().is_ok());
+
+        // Use the refresh token once, then again.
+        for i in 1..=2 {
+            let ct = Duration::from_secs(TEST_CURRENT_TIME + i);
+            let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+            let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
+                refresh_token: refresh_token.clone(),
+                scope: None,
+            }
+            .into();
+            let result = idms_prox_write.check_oauth2_token_exchange(&client_authz, &token_req, ct);
+            if i == 1 {
+                assert!(result.is_ok());
+            } else {
+                assert_eq!(result.unwrap_err(), Oauth2Error::InvalidGrant);
+            }
+
+            assert!(idms_prox_write.commit().is_ok());
+        }
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME + 3);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_TESTPERSON_1)
+            .expect("failed");
+        let sessions = entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .expect("No OAuth2 sessions");
+        assert_eq!(sessions.len(), 2);
+        for session in sessions.values() {
+            assert!(matches!(session.state, SessionState::RevokedAt(_)));
+            assert_eq!(
+                session.revoke_reason,
+                Some(Oauth2SessionRevokeReason::RefreshTokenReuse)
+            );
+        }
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        // The exchanged access token is no longer accepted.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let intr_request = AccessTokenIntrospectRequest {
+            token: exchanged.access_token,
+            token_type_hint: None,
+        };
+        let intr_response = idms_prox_read
+            .check_oauth2_token_introspect(&client_authz, &intr_request, ct)
+            .expect("Failed to inspect token");
+        assert!(!intr_response.active);
+    }
+
+    // Test session divergence. This means that we have to:
+    // access + refresh 1
+    // use refresh 1 -> access + refresh 2 // don't commit this txn.
+    // use refresh 2 -> access + refresh 3
+    //    check the session state.
+
+    #[idm_test]
+    async fn test_idm_oauth2_refresh_token_divergence(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        // First, setup to get a token.
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let (access_token_response_1, client_authz) =
+            setup_refresh_token(idms, idms_delayed, ct).await;
+
+        // ============================================
+        // Use the refresh token once
+        let ct = Duration::from_secs(TEST_CURRENT_TIME + 1);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let refresh_token = access_token_response_1
+            .refresh_token
+            .as_ref()
+            .expect("no refresh token was issued")
+            .clone();
+
+        let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
+            refresh_token,
+            scope: None,
+        }
+        .into();
+
+        let access_token_response_2 = idms_prox_write
+            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+            .expect("Unable to exchange for OAuth2 token");
+
+        // DO NOT COMMIT HERE - this is what forces the session issued_at
+        // time to stay at the original time!
+        drop(idms_prox_write);
+
+        // ============================================
+        let ct = Duration::from_secs(TEST_CURRENT_TIME + 2);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let refresh_token = access_token_response_2
+            .refresh_token
+            .as_ref()
+            .expect("no refresh token was issued")
+            .clone();
+
+        let token_req: AccessTokenRequest = GrantTypeReq::RefreshToken {
+            refresh_token,
+            scope: None,
+        }
+        .into();
+
+        let _access_token_response_3 = idms_prox_write
+            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+            .expect("Unable to exchange for OAuth2 token");
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        // Success!
+    }
+
+    #[idm_test]
+    async fn test_idm_oauth2_refresh_token_scope_constraints(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        // First, setup to get a token.
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let (access_token_response_1, client_authz) =
+            setup_refresh_token(idms, idms_delayed, ct).await;
+
+        // https://www.rfc-editor.org/rfc/rfc6749#section-1.5
+        // Refresh tokens are issued to the client by the authorization
+        // server and are used to obtain a new access token when the
+        // current access token becomes invalid or expires, or to obtain
+        // additional access tokens with identical or narrower scope
+        // (access tokens may have a shorter lifetime and fewer
+        // permissions than authorized by the resource owner).
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME + 10);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let refresh_token = access_token_response_1
+            .refresh_token
+            .as_ref()
+            .expect("no refresh token was issued")
+            .clone();
+
+        // Get the initial scopes.
+        let jws_verifier = JwsDangerReleaseWithoutVerify::default();
 
         let access_token_unverified = JwsCompact::from_str(&access_token_response_1.access_token)
             .expect("Invalid Access Token");
@@ -6748,6 +9140,213 @@ mod tests {
         assert!(idms_prox_write.commit().is_ok());
     }
 
+    #[idm_test]
+    async fn test_idm_oauth2_token_exchange_rfc8693(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+        idms_audit: &mut IdmServerAudit,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let (access_token_response, client_authz) =
+            setup_refresh_token(idms, idms_delayed, ct).await;
+
+        // Setup a downstream client that the user can access through the test group.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let downstream_uuid = Uuid::new_v4();
+        let entry_downstream: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(downstream_uuid)),
+            (Attribute::Name, Value::new_iname("test_downstream")),
+            (Attribute::DisplayName, Value::new_utf8s("test_downstream")),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::new_url_s("https://downstream.example.com").unwrap()
+            ),
+            (
+                Attribute::OAuth2RsScopeMap,
+                Value::new_oauthscopemap(
+                    UUID_TESTGROUP,
+                    btreeset![OAUTH2_SCOPE_GROUPS.to_string()]
+                )
+                .expect("invalid oauthscope")
+            )
+        );
+
+        let ce = CreateEvent::new_internal(vec![entry_downstream]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+
+        let downstream_secret = idms_prox_write
+            .qs_write
+            .internal_search_uuid(downstream_uuid)
+            .ok()
+            .and_then(|entry| {
+                entry
+                    .get_ava_single_secret(Attribute::OAuth2RsBasicSecret)
+                    .map(str::to_string)
+            })
+            .expect("No oauth2_rs_basic_secret found");
+        let downstream_authz =
+            ClientAuthInfo::encode_basic("test_downstream", downstream_secret.as_str());
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let exchange_req = |audience: Option<&str>, scope: Option<BTreeSet<String>>| {
+            AccessTokenRequest::from(GrantTypeReq::TokenExchange {
+                subject_token: access_token_response.access_token.clone(),
+                subject_token_type: OAUTH2_TOKEN_TYPE_ACCESS_TOKEN.to_string(),
+                actor_token: None,
+                actor_token_type: None,
+                requested_token_type: None,
+                audience: audience.map(str::to_string),
+                scope,
+            })
+        };
+
+        // The client may not exchange for another client unless permitted.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert_eq!(
+            idms_prox_write
+                .check_oauth2_token_exchange(
+                    &client_authz,
+                    &exchange_req(Some("test_downstream"), None),
+                    ct
+                )
+                .unwrap_err(),
+            Oauth2Error::InvalidTarget
+        );
+
+        // But it can always reduce the scopes of a token for itself.
+        let downscoped = idms_prox_write
+            .check_oauth2_token_exchange(
+                &client_authz,
+                &exchange_req(None, Some(btreeset!["supplement".to_string()])),
+                ct,
+            )
+            .expect("Failed to exchange token");
+        assert_eq!(downscoped.scope, btreeset!["supplement".to_string()]);
+        assert_eq!(
+            downscoped.issued_token_type.as_deref(),
+            Some(OAUTH2_TOKEN_TYPE_ACCESS_TOKEN)
+        );
+
+        // Scopes can't be added to a token for the same client.
+        assert_eq!(
+            idms_prox_write
+                .check_oauth2_token_exchange(
+                    &client_authz,
+                    &exchange_req(None, Some(btreeset![OAUTH2_SCOPE_GROUPS.to_string()])),
+                    ct
+                )
+                .unwrap_err(),
+            Oauth2Error::InvalidScope
+        );
+
+        // Permit the client to exchange tokens for the downstream client.
+        let rs_uuid = idms_prox_write
+            .qs_write
+            .name_to_uuid("test_resource_server")
+            .expect("Unable to resolve resource server");
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                rs_uuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::OAuth2TokenExchangeAudience,
+                    Value::Refer(downstream_uuid)
+                ),
+            )
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        // Drain the audit events of the down-scoping exchange.
+        while idms_audit.audit_rx().try_recv().is_ok() {}
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        // The user is not granted openid by the downstream client.
+        assert_eq!(
+            idms_prox_write
+                .check_oauth2_token_exchange(
+                    &client_authz,
+                    &exchange_req(
+                        Some("test_downstream"),
+                        Some(btreeset![OAUTH2_SCOPE_OPENID.to_string()])
+                    ),
+                    ct
+                )
+                .unwrap_err(),
+            Oauth2Error::InvalidScope
+        );
+
+        let exchanged = idms_prox_write
+            .check_oauth2_token_exchange(
+                &client_authz,
+                &exchange_req(Some("test_downstream"), None),
+                ct,
+            )
+            .expect("Failed to exchange token");
+        assert_eq!(exchanged.scope, btreeset![OAUTH2_SCOPE_GROUPS.to_string()]);
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        // The exchange is attributed to the requesting client.
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::Oauth2TokenExchanged {
+                uuid,
+                client_id,
+                audience,
+                ..
+            }) => {
+                assert_eq!(uuid, UUID_TESTPERSON_1);
+                assert_eq!(client_id, "test_resource_server");
+                assert_eq!(audience, "test_downstream");
+            }
+            _ => panic!("Oh no"),
+        }
+
+        // The new token is valid for the downstream client.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let intr_request = AccessTokenIntrospectRequest {
+            token: exchanged.access_token.clone(),
+            token_type_hint: None,
+        };
+        let intr_response = idms_prox_read
+            .check_oauth2_token_introspect(&downstream_authz, &intr_request, ct)
+            .expect("Failed to inspect token");
+        assert!(intr_response.active);
+        assert_eq!(intr_response.sub, Some(UUID_TESTPERSON_1.to_string()));
+        drop(idms_prox_read);
+
+        // And an exchanged token can't be exchanged again.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let token_req = AccessTokenRequest::from(GrantTypeReq::TokenExchange {
+            subject_token: exchanged.access_token,
+            subject_token_type: OAUTH2_TOKEN_TYPE_ACCESS_TOKEN.to_string(),
+            actor_token: None,
+            actor_token_type: None,
+            requested_token_type: None,
+            audience: None,
+            scope: None,
+        });
+        assert_eq!(
+            idms_prox_write
+                .check_oauth2_token_exchange(&downstream_authz, &token_req, ct)
+                .unwrap_err(),
+            Oauth2Error::InvalidRequest
+        );
+    }
+
     #[test]
     // I know this looks kinda dumb but at some point someone pointed out that our scope syntax wasn't compliant with rfc6749
     //(https://datatracker.ietf.org/doc/html/rfc6749#section-3.3), so I'm just making sure that we don't break it again.
@@ -6859,10 +9458,21 @@ mod tests {
             OAUTH2_SCOPE_OPENID.to_string()
         );
 
-        let AuthoriseResponse::ConsentRequested { consent_token, .. } = consent_request else {
+        let AuthoriseResponse::ConsentRequested {
+            consent_token,
+            claims,
+            ..
+        } = consent_request
+        else {
             unreachable!();
         };
 
+        // The consent lists the custom claims that apply to this account.
+        assert_eq!(
+            claims,
+            btreeset!["custom_a".to_string(), "custom_b".to_string()]
+        );
+
         // == Manually submit the consent token to the permit for the permit_success
         drop(idms_prox_read);
         let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
@@ -6928,7 +9538,7 @@ mod tests {
         assert_eq!(oidc.nbf, Some(iat));
         // Previously this was the auth session but it's now inline with the access token expiry.
         assert_eq!(oidc.exp, iat + (OAUTH2_ACCESS_TOKEN_EXPIRY as i64));
-        assert!(oidc.auth_time.is_none());
+        assert_eq!(oidc.auth_time, Some(iat));
         // Is nonce correctly passed through?
         assert_eq!(oidc.nonce, Some("abcdef".to_string()));
         assert!(oidc.at_hash.is_none());
@@ -6957,7 +9567,7 @@ mod tests {
         // Does our access token work with the userinfo endpoint?
         // Do the id_token details line up to the userinfo?
         let userinfo = idms_prox_read
-            .oauth2_openid_userinfo("test_resource_server", access_token, ct)
+            .oauth2_openid_userinfo("test_resource_server", access_token, None, None, ct)
             .expect("failed to get userinfo");
 
         assert_eq!(oidc.iss, userinfo.iss);
@@ -7008,12 +9618,171 @@ mod tests {
     }
 
     #[idm_test]
-    async fn test_idm_oauth2_public_allow_localhost_redirect(
+    async fn test_idm_oauth2_computed_claims(
         idms: &IdmServer,
         _idms_delayed: &mut IdmServerDelayed,
     ) {
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
-        let (_uat, ident, oauth2_rs_uuid) = setup_oauth2_resource_server_public(idms, ct).await;
+        let (secret, _uat, ident, oauth2_rs_uuid) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, false).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let modlist = ModifyList::new_list(vec![
+            Modify::Present(
+                Attribute::OAuth2RsComputedClaim,
+                Value::new_utf8s("alias=template:{name}.{uuid}@Corp|lowercase"),
+            ),
+            // An absent attribute means no value is produced.
+            Modify::Present(
+                Attribute::OAuth2RsComputedClaim,
+                Value::new_utf8s("legal=template:{legalname}"),
+            ),
+            Modify::Present(
+                Attribute::OAuth2RsComputedClaim,
+                Value::new_utf8s("roles=groups|filter_prefix:test|strip_prefix:test"),
+            ),
+            Modify::Present(
+                Attribute::OAuth2RsComputedClaim,
+                Value::new_utf8s("all=groups|filter_prefix:idm_all_|strip_prefix:idm_all_|join:;"),
+            ),
+            Modify::Present(
+                Attribute::OAuth2RsComputedClaim,
+                Value::new_utf8s("globbed=groups|filter_glob:test?rou*"),
+            ),
+            // Invalid attributes can't be used in templates.
+            Modify::Present(
+                Attribute::OAuth2RsComputedClaim,
+                Value::new_utf8s("secret=template:{radius_secret}"),
+            ),
+        ]);
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify(
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(oauth2_rs_uuid))),
+                &modlist,
+            )
+            .is_ok());
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let client_authz = ClientAuthInfo::encode_basic("test_resource_server", secret.as_str());
+
+        let idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let (code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+
+        let consent_request = good_authorisation_request!(
+            idms_prox_read,
+            &ident,
+            ct,
+            code_challenge,
+            OAUTH2_SCOPE_OPENID.to_string()
+        );
+
+        let AuthoriseResponse::ConsentRequested {
+            consent_token,
+            claims,
+            ..
+        } = consent_request
+        else {
+            unreachable!();
+        };
+
+        assert_eq!(
+            claims,
+            btreeset![
+                "alias".to_string(),
+                "all".to_string(),
+                "globbed".to_string(),
+                "legal".to_string(),
+                "roles".to_string()
+            ]
+        );
+
+        drop(idms_prox_read);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let permit_success = idms_prox_write
+            .check_oauth2_authorise_permit(&ident, &consent_token, ct)
+            .expect("Failed to perform OAuth2 permit");
+
+        let token_req: AccessTokenRequest = GrantTypeReq::AuthorizationCode {
+            code: permit_success.code,
+            redirect_uri: Url::parse("https://demo.example.com/oauth2/result").unwrap(),
+            code_verifier,
+        }
+        .into();
+
+        let token_response = idms_prox_write
+            .check_oauth2_token_exchange(&client_authz, &token_req, ct)
+            .expect("Failed to perform OAuth2 token exchange");
+
+        let id_token = token_response.id_token.expect("No id_token in response!");
+        let access_token =
+            JwsCompact::from_str(&token_response.access_token).expect("Invalid Access Token");
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let mut jwkset = idms_prox_read
+            .oauth2_openid_publickey("test_resource_server")
+            .expect("Failed to get public key");
+
+        let public_jwk = jwkset.keys.pop().expect("no such jwk");
+
+        let jws_validator =
+            JwsEs256Verifier::try_from(&public_jwk).expect("failed to build validator");
+
+        let oidc_unverified =
+            OidcUnverified::from_str(&id_token).expect("Failed to parse id_token");
+
+        let oidc = jws_validator
+            .verify(&oidc_unverified)
+            .unwrap()
+            .verify_exp(ct.as_secs() as i64)
+            .expect("Failed to verify oidc");
+
+        let expected_alias = format!("testperson1.{UUID_TESTPERSON_1}@corp");
+        assert_eq!(
+            oidc.claims.get("alias").and_then(|v| v.as_str()),
+            Some(expected_alias.as_str())
+        );
+        assert!(!oidc.claims.contains_key("legal"));
+        assert!(!oidc.claims.contains_key("secret"));
+        assert_eq!(
+            oidc.claims.get("roles"),
+            Some(&serde_json::json!(["group"]))
+        );
+        let all = oidc
+            .claims
+            .get("all")
+            .and_then(|v| v.as_str())
+            .expect("No all claim");
+        assert!(all.split(';').any(|group| group == "accounts"));
+        assert!(all.split(';').any(|group| group == "persons"));
+        assert_eq!(
+            oidc.claims.get("globbed"),
+            Some(&serde_json::json!(["testgroup"]))
+        );
+
+        // The userinfo endpoint has the same claims.
+        let userinfo = idms_prox_read
+            .oauth2_openid_userinfo("test_resource_server", access_token, None, None, ct)
+            .expect("failed to get userinfo");
+
+        assert_eq!(oidc.claims, userinfo.claims);
+    }
+
+    #[idm_test]
+    async fn test_idm_oauth2_public_allow_localhost_redirect(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (_uat, ident, oauth2_rs_uuid) = setup_oauth2_resource_server_public(idms, ct).await;
 
         let mut idms_prox_write: crate::idm::server::IdmServerProxyWriteTransaction<'_> =
             idms.proxy_write(ct).await.unwrap();
@@ -7101,9 +9870,246 @@ mod tests {
     }
 
     #[idm_test]
+    async fn test_idm_oauth2_redirect_uri_modes(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (_uat, ident, oauth2_rs_uuid) = setup_oauth2_resource_server_public(idms, ct).await;
+
+        let ident = &ident;
+
+        let set_modlist = |modlist: ModifyList<ModifyInvalid>| async move {
+            let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+            assert!(idms_prox_write
+                .qs_write
+                .internal_modify_uuid(oauth2_rs_uuid, &modlist)
+                .is_ok());
+            assert!(idms_prox_write.commit().is_ok());
+        };
+
+        let check = |redirect_uri: &'static str| async move {
+            let (_code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+            let auth_req = AuthorisationRequest {
+                response_type: ResponseType::Code,
+                response_mode: None,
+                client_id: "test_resource_server".to_string(),
+                state: Some("123".to_string()),
+                pkce_request: Some(PkceRequest {
+                    code_challenge,
+                    code_challenge_method: CodeChallengeMethod::S256,
+                }),
+                redirect_uri: Url::parse(redirect_uri).unwrap(),
+                scope: btreeset![OAUTH2_SCOPE_OPENID.to_string()],
+                nonce: None,
+                oidc_ext: Default::default(),
+                max_age: None,
+                unknown_keys: Default::default(),
+            };
+
+            let idms_prox_read = idms.proxy_read().await.unwrap();
+            idms_prox_read
+                .check_oauth2_authorisation(Some(&ident), &auth_req, ct)
+                .map(|_| ())
+        };
+
+        set_modlist(ModifyList::new_list(vec![
+            Modify::Present(
+                Attribute::OAuth2RsOrigin,
+                Value::new_url_s("http://127.0.0.1:8765/oauth2/result").unwrap(),
+            ),
+            Modify::Present(
+                Attribute::OAuth2RsOrigin,
+                Value::new_url_s("com.example.app:/oauth2/result").unwrap(),
+            ),
+        ]))
+        .await;
+
+        // By default only exact matches are allowed, and as the client has https redirect uris
+        // the loopback redirect uri is rejected.
+        assert!(check("com.example.app:/oauth2/result").await.is_ok());
+        assert_eq!(
+            check("http://127.0.0.1:8765/oauth2/result").await,
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0003RedirectUriHttpsRequired
+            ))
+        );
+        assert_eq!(
+            check("http://127.0.0.1:9000/oauth2/result").await,
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0001RedirectUriNotRegistered
+            ))
+        );
+        assert_eq!(
+            check("com.example.app:/oauth2/other").await,
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0001RedirectUriNotRegistered
+            ))
+        );
+
+        // Loopback redirects may use any port, but must otherwise match.
+        set_modlist(ModifyList::new_purge_and_set(
+            Attribute::OAuth2RedirectUriMode,
+            Value::new_iutf8("loopback_any_port"),
+        ))
+        .await;
+
+        assert!(check("http://127.0.0.1:8765/oauth2/result").await.is_ok());
+        assert!(check("http://127.0.0.1:9000/oauth2/result").await.is_ok());
+        assert!(check("http://127.0.0.1/oauth2/result").await.is_ok());
+        assert_eq!(
+            check("http://127.0.0.1:9000/oauth2/other").await,
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0004RedirectUriLoopbackNotRegistered
+            ))
+        );
+        assert_eq!(
+            check("http://[::1]:9000/oauth2/result").await,
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0004RedirectUriLoopbackNotRegistered
+            ))
+        );
+        // Other hosts still require an exact match.
+        assert_eq!(
+            check("https://demo.example.com:8443/oauth2/result").await,
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0001RedirectUriNotRegistered
+            ))
+        );
+
+        // Any path of a registered private-use scheme is allowed.
+        set_modlist(ModifyList::new_purge_and_set(
+            Attribute::OAuth2RedirectUriMode,
+            Value::new_iutf8("private_use_scheme"),
+        ))
+        .await;
+
+        assert!(check("com.example.app:/oauth2/other").await.is_ok());
+        assert_eq!(
+            check("org.example.other:/oauth2/result").await,
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0006RedirectUriPrivateUseSchemeNotRegistered
+            ))
+        );
+        assert_eq!(
+            check("myapp:/oauth2/result").await,
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0005RedirectUriPrivateUseSchemeInvalid
+            ))
+        );
+        // The loopback allowance is no longer in effect.
+        assert_eq!(
+            check("http://127.0.0.1:9000/oauth2/result").await,
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0001RedirectUriNotRegistered
+            ))
+        );
+    }
+
+    #[idm_test]
+    async fn test_idm_oauth2_native_client(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (_uat, ident, oauth2_rs_uuid) = setup_oauth2_resource_server_public(idms, ct).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                oauth2_rs_uuid,
+                &ModifyList::new_list(vec![
+                    Modify::Present(
+                        Attribute::Class,
+                        EntryClass::OAuth2ResourceServerNative.to_value(),
+                    ),
+                    Modify::Present(
+                        Attribute::OAuth2RsOrigin,
+                        Value::new_url_s("http://127.0.0.1:8765/oauth2/result").unwrap(),
+                    ),
+                    Modify::Present(
+                        Attribute::OAuth2RsOrigin,
+                        Value::new_url_s("com.example.app:/oauth2/result").unwrap(),
+                    ),
+                ])
+            )
+            .is_ok());
+
+        // A native client can never be given a secret.
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                oauth2_rs_uuid,
+                &ModifyList::new_append(
+                    Attribute::Class,
+                    EntryClass::OAuth2ResourceServerBasic.to_value()
+                )
+            )
+            .is_err());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let o2rs = idms_prox_read
+            .oauth2rs
+            .inner
+            .rs_set
+            .get("test_resource_server")
+            .expect("Client not found");
+        assert!(o2rs.is_native());
+        assert!(o2rs.require_pkce());
+        assert_eq!(
+            o2rs.access_token_expiry(),
+            OAUTH2_NATIVE_ACCESS_TOKEN_EXPIRY
+        );
+        assert_eq!(
+            o2rs.refresh_token_expiry(),
+            OAUTH2_NATIVE_REFRESH_TOKEN_EXPIRY
+        );
+
+        let check = |redirect_uri: &'static str, pkce: bool| {
+            let (_code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+            let auth_req = AuthorisationRequest {
+                response_type: ResponseType::Code,
+                response_mode: None,
+                client_id: "test_resource_server".to_string(),
+                state: Some("123".to_string()),
+                pkce_request: pkce.then_some(PkceRequest {
+                    code_challenge,
+                    code_challenge_method: CodeChallengeMethod::S256,
+                }),
+                redirect_uri: Url::parse(redirect_uri).unwrap(),
+                scope: btreeset![OAUTH2_SCOPE_OPENID.to_string()],
+                nonce: None,
+                oidc_ext: Default::default(),
+                max_age: None,
+                unknown_keys: Default::default(),
+            };
+
+            idms_prox_read
+                .check_oauth2_authorisation(Some(&ident), &auth_req, ct)
+                .map(|_| ())
+        };
+
+        // Loopback redirects on any port, and any path of the registered scheme are allowed.
+        assert!(check("http://127.0.0.1:9000/oauth2/result", true).is_ok());
+        assert!(check("com.example.app:/oauth2/other", true).is_ok());
+        assert_eq!(
+            check("org.example.other:/oauth2/result", true),
+            Err(Oauth2Error::RedirectUriRejected(
+                OperationError::OA0006RedirectUriPrivateUseSchemeNotRegistered
+            ))
+        );
+        // PKCE is always required.
+        assert_eq!(
+            check("com.example.app:/oauth2/result", false),
+            Err(Oauth2Error::InvalidRequest)
+        );
+    }
+
+    #[idm_test(audit = 1)]
     async fn test_idm_oauth2_basic_client_credentials_grant_valid(
         idms: &IdmServer,
         _idms_delayed: &mut IdmServerDelayed,
+        idms_audit: &mut IdmServerAudit,
     ) {
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
         let (secret, _uat, _ident, _) =
@@ -7128,6 +10134,36 @@ mod tests {
         // 🎉 We got a token! In the future we can then check introspection from this point.
         assert_eq!(oauth2_token.token_type, AccessTokenType::Bearer);
 
+        // The grant was recorded to the audit queue.
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::Oauth2ClientCredentialsGranted { client_id, .. }) => {
+                assert_eq!(client_id, "test_resource_server");
+            }
+            _ => panic!("Oh no"),
+        }
+
+        // And the session is visible on the resource server.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let rs_uuid = idms_prox_read
+            .qs_read
+            .name_to_uuid("test_resource_server")
+            .expect("Unable to resolve resource server");
+
+        let lse = ListOauth2SessionEvent {
+            ident: Identity::from_internal(),
+            target: rs_uuid,
+        };
+        let sessions = idms_prox_read
+            .oauth2_list_sessions(&lse)
+            .expect("Failed to list sessions");
+
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].rs_uuid, rs_uuid);
+        assert!(sessions[0].parent_session_id.is_none());
+
+        drop(idms_prox_read);
+
         // Check Oauth2 Token Introspection
         let mut idms_prox_read = idms.proxy_read().await.unwrap();
 
@@ -7256,15 +10292,304 @@ mod tests {
         assert!(idms_prox_write.commit().is_ok());
     }
 
-    #[test]
-    fn test_get_code() {
-        use super::{gen_device_code, gen_user_code, parse_user_code};
-
-        assert!(gen_device_code().is_ok());
+    #[idm_test]
+    async fn test_idm_oauth2_dynamic_client_registration(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let registrar_uuid = Uuid::new_v4();
 
-        let (res_string, res_value) = gen_user_code();
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
 
-        assert!(res_string.split('-').count() == 3);
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_registrar")),
+            (Attribute::Uuid, Value::Uuid(registrar_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("test_registrar"))
+        );
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e1])
+            .expect("Failed to create service account");
+
+        let registrar_ident =
+            |idms_prox_write: &mut crate::idm::server::IdmServerProxyWriteTransaction<'_>| {
+                let account = idms_prox_write
+                    .target_to_account(registrar_uuid)
+                    .expect("account must exist");
+                let uat = account
+                    .to_userauthtoken(
+                        Uuid::new_v4(),
+                        SessionScope::ReadWrite,
+                        ct,
+                        &ResolvedAccountPolicy::test_policy(),
+                    )
+                    .expect("Unable to create uat");
+                idms_prox_write
+                    .process_uat_to_identity(&uat, ct, Source::Internal)
+                    .expect("Unable to process uat")
+            };
+
+        let ident = registrar_ident(&mut idms_prox_write);
+
+        let reg_req = ClientRegistrationRequest {
+            redirect_uris: vec![Url::parse("https://app.example.com/oauth2/callback").unwrap()],
+            token_endpoint_auth_method: None,
+            client_name: Some("Registered App".to_string()),
+            application_type: None,
+            scope: None,
+        };
+
+        // Not a member of the registrars group, so this is denied.
+        assert_eq!(
+            idms_prox_write
+                .oauth2_client_register(&ident, &reg_req, ct)
+                .unwrap_err(),
+            Oauth2Error::AccessDenied
+        );
+
+        // Delegate registration rights.
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_OAUTH2_CLIENT_REGISTRARS,
+                &ModifyList::new_append(Attribute::Member, Value::Refer(registrar_uuid)),
+            )
+            .expect("Unable to add member");
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let ident = registrar_ident(&mut idms_prox_write);
+
+        // Redirect uris are required.
+        let empty_req = ClientRegistrationRequest {
+            redirect_uris: Vec::with_capacity(0),
+            token_endpoint_auth_method: None,
+            client_name: None,
+            application_type: None,
+            scope: None,
+        };
+        assert_eq!(
+            idms_prox_write
+                .oauth2_client_register(&ident, &empty_req, ct)
+                .unwrap_err(),
+            Oauth2Error::InvalidRedirectUri
+        );
+
+        let reg_res = idms_prox_write
+            .oauth2_client_register(&ident, &reg_req, ct)
+            .expect("Failed to register client");
+        assert!(idms_prox_write.commit().is_ok());
+
+        assert_eq!(reg_res.client_name, "Registered App");
+        assert_eq!(
+            reg_res.token_endpoint_auth_method,
+            TokenEndpointAuthMethod::ClientSecretBasic
+        );
+        assert_eq!(reg_res.client_secret_expires_at, Some(0));
+        let secret = reg_res.client_secret.expect("No client secret issued");
+
+        // The client is now usable.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let o2rs = idms_prox_read
+            .oauth2rs
+            .inner
+            .rs_set
+            .get(&reg_res.client_id)
+            .expect("Registered client not loaded");
+        assert!(o2rs.is_basic());
+        assert!(o2rs.strict_redirect_uri);
+
+        let entry = idms_prox_read
+            .qs_read
+            .internal_search(filter!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname(&reg_res.client_id)
+            )))
+            .expect("Unable to search")
+            .pop()
+            .expect("No entry");
+        assert_eq!(
+            entry.get_ava_single_secret(Attribute::OAuth2RsBasicSecret),
+            Some(secret.as_str())
+        );
+        // No scopes were requested, so none are granted.
+        assert!(entry
+            .get_ava_as_oauthscopemaps(Attribute::OAuth2RsScopeMap)
+            .is_none());
+        drop(idms_prox_read);
+
+        // The registrar is not granted any scopes, so it can't grant them to the client.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let ident = registrar_ident(&mut idms_prox_write);
+
+        let scoped_req = ClientRegistrationRequest {
+            redirect_uris: vec![Url::parse("https://app.example.com/oauth2/callback").unwrap()],
+            token_endpoint_auth_method: None,
+            client_name: None,
+            application_type: None,
+            scope: Some(btreeset![
+                OAUTH2_SCOPE_OPENID.to_string(),
+                OAUTH2_SCOPE_EMAIL.to_string()
+            ]),
+        };
+        assert_eq!(
+            idms_prox_write
+                .oauth2_client_register(&ident, &scoped_req, ct)
+                .unwrap_err(),
+            Oauth2Error::InvalidClientMetadata
+        );
+
+        // A group of the registrar is granted openid and email by an existing client.
+        let team_uuid = Uuid::new_v4();
+        let e_team = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("test_registrar_team")),
+            (Attribute::Uuid, Value::Uuid(team_uuid)),
+            (Attribute::Member, Value::Refer(registrar_uuid))
+        );
+        let e_rs = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.to_value()
+            ),
+            (Attribute::Name, Value::new_iname("test_team_app")),
+            (Attribute::DisplayName, Value::new_utf8s("test_team_app")),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::new_url_s("https://team.example.com").unwrap()
+            ),
+            (
+                Attribute::OAuth2RsScopeMap,
+                Value::new_oauthscopemap(
+                    team_uuid,
+                    btreeset![
+                        OAUTH2_SCOPE_OPENID.to_string(),
+                        OAUTH2_SCOPE_EMAIL.to_string()
+                    ]
+                )
+                .expect("invalid oauthscope")
+            )
+        );
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e_team, e_rs])
+            .expect("Failed to create entries");
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let ident = registrar_ident(&mut idms_prox_write);
+
+        // Scopes beyond those granted to the registrar are still refused.
+        let overscoped_req = ClientRegistrationRequest {
+            redirect_uris: vec![Url::parse("https://app.example.com/oauth2/callback").unwrap()],
+            token_endpoint_auth_method: None,
+            client_name: None,
+            application_type: None,
+            scope: Some(btreeset![
+                OAUTH2_SCOPE_OPENID.to_string(),
+                OAUTH2_SCOPE_GROUPS.to_string()
+            ]),
+        };
+        assert_eq!(
+            idms_prox_write
+                .oauth2_client_register(&ident, &overscoped_req, ct)
+                .unwrap_err(),
+            Oauth2Error::InvalidClientMetadata
+        );
+
+        let reg_res = idms_prox_write
+            .oauth2_client_register(&ident, &scoped_req, ct)
+            .expect("Failed to register client");
+        assert_eq!(reg_res.scope, scoped_req.scope);
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search(filter!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname(&reg_res.client_id)
+            )))
+            .expect("Unable to search")
+            .pop()
+            .expect("No entry");
+        let scope_maps = entry
+            .get_ava_as_oauthscopemaps(Attribute::OAuth2RsScopeMap)
+            .expect("No scope maps");
+        assert_eq!(scope_maps.len(), 1);
+        assert_eq!(scope_maps.get(&team_uuid), scoped_req.scope.as_ref());
+
+        // Redirect uris that the client would not be permitted to use are refused.
+        for (redirect_uri, application_type) in [
+            ("http://app.example.com/oauth2/callback", None),
+            ("https://localhost/oauth2/callback", None),
+            ("http://127.0.0.1:8080/oauth2/callback", None),
+            ("com.example.app:/oauth2/callback", None),
+            (
+                "http://app.example.com/oauth2/callback",
+                Some(ApplicationType::Native),
+            ),
+            ("app:/oauth2/callback", Some(ApplicationType::Native)),
+        ] {
+            let reg_req = ClientRegistrationRequest {
+                redirect_uris: vec![Url::parse(redirect_uri).unwrap()],
+                token_endpoint_auth_method: None,
+                client_name: None,
+                application_type,
+                scope: None,
+            };
+            assert_eq!(
+                idms_prox_write
+                    .oauth2_client_register(&ident, &reg_req, ct)
+                    .unwrap_err(),
+                Oauth2Error::InvalidRedirectUri,
+                "{redirect_uri} was permitted"
+            );
+        }
+
+        // Native clients may use loopback and private-use scheme redirects.
+        let native_req = ClientRegistrationRequest {
+            redirect_uris: vec![
+                Url::parse("http://127.0.0.1/oauth2/callback").unwrap(),
+                Url::parse("http://localhost/oauth2/callback").unwrap(),
+                Url::parse("com.example.app:/oauth2/callback").unwrap(),
+            ],
+            token_endpoint_auth_method: None,
+            client_name: None,
+            application_type: Some(ApplicationType::Native),
+            scope: None,
+        };
+        let reg_res = idms_prox_write
+            .oauth2_client_register(&ident, &native_req, ct)
+            .expect("Failed to register native client");
+        assert_eq!(
+            reg_res.token_endpoint_auth_method,
+            TokenEndpointAuthMethod::None
+        );
+        assert!(reg_res.client_secret.is_none());
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    #[test]
+    fn test_get_code() {
+        use super::{gen_device_code, gen_user_code, parse_user_code};
+
+        assert!(gen_device_code().is_ok());
+
+        let (res_string, res_value) = gen_user_code();
+
+        assert!(res_string.split('-').count() == 3);
 
         let res_string_clean = res_string.replace("-", "");
         let res_string_as_num = res_string_clean
@@ -7342,18 +10667,25 @@ mod tests {
             (
                 OauthRSType::Public {
                     allow_localhost_redirect: true,
+                    native: false,
                 },
                 true,
             ),
             (
                 OauthRSType::Public {
                     allow_localhost_redirect: false,
+                    native: true,
                 },
                 false,
             ),
             (
                 OauthRSType::Basic {
-                    authz_secret: "supersecret".to_string(),
+                    authz_secret: Oauth2ClientSecret {
+                        secret: "supersecret".to_string(),
+                        expiry: None,
+                        last_used: None,
+                    },
+                    previous_authz_secret: None,
                     enable_pkce: false,
                 },
                 false,
@@ -7366,4 +10698,429 @@ mod tests {
             actual == *expected
         }));
     }
+
+    #[idm_test]
+    async fn test_idm_oauth2_authorisation_prompt(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (_secret, _uat, ident, _) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, false).await;
+
+        let (_code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+
+        let auth_req = |prompt: Option<&str>, max_age: Option<i64>| AuthorisationRequest {
+            response_type: ResponseType::Code,
+            response_mode: None,
+            client_id: "test_resource_server".to_string(),
+            state: Some("123".to_string()),
+            pkce_request: Some(PkceRequest {
+                code_challenge: code_challenge.clone(),
+                code_challenge_method: CodeChallengeMethod::S256,
+            }),
+            redirect_uri: Url::parse("https://demo.example.com/oauth2/result").unwrap(),
+            scope: btreeset![OAUTH2_SCOPE_OPENID.to_string()],
+            nonce: None,
+            oidc_ext: AuthorisationRequestOidc {
+                prompt: prompt.map(str::to_string),
+                login_hint: Some("testperson1".to_string()),
+                ..Default::default()
+            },
+            max_age,
+            unknown_keys: Default::default(),
+        };
+
+        let idms_prox_read = idms.proxy_read().await.unwrap();
+
+        // prompt=none without a session is returned to the client.
+        let Ok(AuthoriseResponse::Rejected(reject)) =
+            idms_prox_read.check_oauth2_authorisation(None, &auth_req(Some("none"), None), ct)
+        else {
+            unreachable!();
+        };
+        assert_eq!(reject.error, Oauth2Error::LoginRequired);
+        let redirect_uri = reject.build_redirect_uri();
+        assert!(redirect_uri
+            .query_pairs()
+            .any(|(k, v)| k == "error" && v == "login_required"));
+        assert!(redirect_uri
+            .query_pairs()
+            .any(|(k, v)| k == "state" && v == "123"));
+
+        // The user has not consented yet, so prompt=none can't proceed.
+        let Ok(AuthoriseResponse::Rejected(reject)) = idms_prox_read.check_oauth2_authorisation(
+            Some(&ident),
+            &auth_req(Some("none"), None),
+            ct,
+        ) else {
+            unreachable!();
+        };
+        assert_eq!(reject.error, Oauth2Error::ConsentRequired);
+
+        // prompt=login forces authentication, and retains the login hint.
+        let Ok(AuthoriseResponse::AuthenticationRequired { login_hint, .. }) = idms_prox_read
+            .check_oauth2_authorisation(Some(&ident), &auth_req(Some("login"), None), ct)
+        else {
+            unreachable!();
+        };
+        assert_eq!(login_hint.as_deref(), Some("testperson1"));
+
+        // The session was authenticated at ct, so a max_age that has passed requires a login.
+        let later = ct + Duration::from_secs(120);
+        assert!(matches!(
+            idms_prox_read.check_oauth2_authorisation(
+                Some(&ident),
+                &auth_req(None, Some(60)),
+                later
+            ),
+            Ok(AuthoriseResponse::AuthenticationRequired { .. })
+        ));
+        assert!(matches!(
+            idms_prox_read.check_oauth2_authorisation(
+                Some(&ident),
+                &auth_req(None, Some(300)),
+                later
+            ),
+            Ok(AuthoriseResponse::ConsentRequested { .. })
+        ));
+        assert!(matches!(
+            idms_prox_read.check_oauth2_authorisation(
+                Some(&ident),
+                &auth_req(Some("none"), Some(60)),
+                later
+            ),
+            Ok(AuthoriseResponse::Rejected(AuthoriseReject {
+                error: Oauth2Error::LoginRequired,
+                ..
+            }))
+        ));
+
+        // Invalid combinations are refused.
+        assert_eq!(
+            idms_prox_read
+                .check_oauth2_authorisation(Some(&ident), &auth_req(Some("none login"), None), ct)
+                .unwrap_err(),
+            Oauth2Error::InvalidRequest
+        );
+        assert_eq!(
+            idms_prox_read
+                .check_oauth2_authorisation(Some(&ident), &auth_req(None, Some(-1)), ct)
+                .unwrap_err(),
+            Oauth2Error::InvalidRequest
+        );
+    }
+
+    #[idm_test]
+    async fn test_idm_oauth2_allowed_grant_and_response_types(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (secret, _uat, ident, rs_uuid) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, false).await;
+
+        let ident = &ident;
+
+        let client_credentials = |secret: String| async move {
+            let token_req = AccessTokenRequest {
+                grant_type: GrantTypeReq::ClientCredentials { scope: None },
+                client_id: Some("test_resource_server".to_string()),
+                client_secret: Some(secret),
+            };
+            let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+            let res = idms_prox_write
+                .check_oauth2_token_exchange(&ClientAuthInfo::none(), &token_req, ct)
+                .map(|_| ());
+            assert!(idms_prox_write.commit().is_ok());
+            res
+        };
+
+        let authorise = || async move {
+            let (_code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+            let auth_req = AuthorisationRequest {
+                response_type: ResponseType::Code,
+                response_mode: None,
+                client_id: "test_resource_server".to_string(),
+                state: Some("123".to_string()),
+                pkce_request: Some(PkceRequest {
+                    code_challenge,
+                    code_challenge_method: CodeChallengeMethod::S256,
+                }),
+                redirect_uri: Url::parse("https://demo.example.com/oauth2/result").unwrap(),
+                scope: btreeset![OAUTH2_SCOPE_OPENID.to_string()],
+                nonce: None,
+                oidc_ext: Default::default(),
+                max_age: None,
+                unknown_keys: Default::default(),
+            };
+            let idms_prox_read = idms.proxy_read().await.unwrap();
+            idms_prox_read
+                .check_oauth2_authorisation(Some(&ident), &auth_req, ct)
+                .map(|_| ())
+        };
+
+        // Without an allow list, every grant and response type is permitted.
+        assert!(client_credentials(secret.clone()).await.is_ok());
+        assert!(authorise().await.is_ok());
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let modlist = ModifyList::new_list(vec![
+            Modify::Present(
+                Attribute::OAuth2AllowedGrantTypes,
+                Value::new_iutf8("authorization_code"),
+            ),
+            Modify::Present(
+                Attribute::OAuth2AllowedResponseTypes,
+                Value::new_iutf8("token"),
+            ),
+        ]);
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(rs_uuid, &modlist)
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        assert_eq!(
+            client_credentials(secret.clone()).await,
+            Err(Oauth2Error::UnauthorizedClient)
+        );
+        assert_eq!(authorise().await, Err(Oauth2Error::UnauthorizedClient));
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let modlist = ModifyList::new_list(vec![
+            Modify::Present(
+                Attribute::OAuth2AllowedGrantTypes,
+                Value::new_iutf8("client_credentials"),
+            ),
+            Modify::Present(
+                Attribute::OAuth2AllowedResponseTypes,
+                Value::new_iutf8("code"),
+            ),
+        ]);
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(rs_uuid, &modlist)
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        assert!(client_credentials(secret).await.is_ok());
+        assert!(authorise().await.is_ok());
+    }
+
+    #[idm_test]
+    async fn test_idm_oauth2_consent_scope_descriptions(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (_secret, _uat, ident, rs_uuid) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, false).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let modlist = ModifyList::new_list(
+            [
+                "openid=Sign in|Confirm who you are",
+                "openid@de=Anmelden",
+                "unrelated=Not requested",
+                // Invalid, and ignored.
+                "=No scope",
+            ]
+            .into_iter()
+            .map(|desc| {
+                Modify::Present(Attribute::OAuth2RsScopeDescription, Value::new_utf8s(desc))
+            })
+            .collect(),
+        );
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(rs_uuid, &modlist)
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let idms_prox_read = idms.proxy_read().await.unwrap();
+        let (_code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+
+        let consent_request = good_authorisation_request!(
+            idms_prox_read,
+            &ident,
+            ct,
+            code_challenge,
+            OAUTH2_SCOPE_OPENID.to_string()
+        );
+
+        let AuthoriseResponse::ConsentRequested {
+            scope_descriptions, ..
+        } = consent_request
+        else {
+            unreachable!();
+        };
+
+        // Only the descriptions of the requested scopes are returned.
+        assert_eq!(scope_descriptions.len(), 2);
+        assert!(scope_descriptions
+            .iter()
+            .all(|desc| desc.scope == OAUTH2_SCOPE_OPENID));
+        assert!(scope_descriptions.iter().any(|desc| {
+            desc.language.is_none()
+                && desc.title == "Sign in"
+                && desc.description.as_deref() == Some("Confirm who you are")
+        }));
+        assert!(scope_descriptions
+            .iter()
+            .any(|desc| desc.language.as_deref() == Some("de") && desc.title == "Anmelden"));
+    }
+
+    #[idm_test]
+    async fn test_idm_oauth2_basic_secret_rotation(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (old_secret, _uat, _ident, rs_uuid) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, false).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        idms_prox_write
+            .oauth2_basic_secret_rotate(
+                &Identity::from_internal(),
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(rs_uuid))),
+                Some(Duration::from_secs(60)),
+                ct,
+            )
+            .expect("Failed to rotate secret");
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(rs_uuid)
+            .expect("Failed to find resource server");
+        let new_secret = entry
+            .get_ava_single_secret(Attribute::OAuth2RsBasicSecret)
+            .map(str::to_string)
+            .expect("No new secret was generated");
+        assert_ne!(new_secret, old_secret);
+        assert_eq!(
+            entry.get_ava_single_datetime(Attribute::OAuth2RsBasicSecretPreviousExpiry),
+            Some(OffsetDateTime::UNIX_EPOCH + ct + Duration::from_secs(60))
+        );
+        assert!(idms_prox_write.commit().is_ok());
+
+        let token_exchange = |secret: &str| AccessTokenRequest {
+            grant_type: GrantTypeReq::ClientCredentials { scope: None },
+            client_id: Some("test_resource_server".to_string()),
+            client_secret: Some(secret.to_string()),
+        };
+
+        // Both secrets are valid until the previous one expires, and their use is recorded.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        for secret in [&old_secret, &new_secret] {
+            assert!(idms_prox_write
+                .check_oauth2_token_exchange(&ClientAuthInfo::none(), &token_exchange(secret), ct)
+                .is_ok());
+        }
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(rs_uuid)
+            .expect("Failed to find resource server");
+        let odt_ct = Some(OffsetDateTime::UNIX_EPOCH + ct);
+        assert_eq!(
+            entry.get_ava_single_datetime(Attribute::OAuth2RsBasicSecretLastUsed),
+            odt_ct
+        );
+        assert_eq!(
+            entry.get_ava_single_datetime(Attribute::OAuth2RsBasicSecretPreviousLastUsed),
+            odt_ct
+        );
+        assert!(idms_prox_write.commit().is_ok());
+
+        // Once the previous secret expires, only the new secret is accepted.
+        let ct = ct + Duration::from_secs(120);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert_eq!(
+            idms_prox_write
+                .check_oauth2_token_exchange(
+                    &ClientAuthInfo::none(),
+                    &token_exchange(&old_secret),
+                    ct
+                )
+                .unwrap_err(),
+            Oauth2Error::AuthenticationRequired
+        );
+        assert!(idms_prox_write
+            .check_oauth2_token_exchange(&ClientAuthInfo::none(), &token_exchange(&new_secret), ct)
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    #[idm_test]
+    async fn test_idm_oauth2_assigned_members(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let (_secret, _uat, ident, rs_uuid) =
+            setup_oauth2_resource_server_basic(idms, ct, true, false, false).await;
+
+        let ident = &ident;
+
+        let authorise = || async move {
+            let (_code_verifier, code_challenge) = create_code_verifier!("Whar Garble");
+            let auth_req = AuthorisationRequest {
+                response_type: ResponseType::Code,
+                response_mode: None,
+                client_id: "test_resource_server".to_string(),
+                state: Some("123".to_string()),
+                pkce_request: Some(PkceRequest {
+                    code_challenge,
+                    code_challenge_method: CodeChallengeMethod::S256,
+                }),
+                redirect_uri: Url::parse("https://demo.example.com/oauth2/result").unwrap(),
+                scope: btreeset![OAUTH2_SCOPE_OPENID.to_string()],
+                nonce: None,
+                oidc_ext: Default::default(),
+                max_age: None,
+                unknown_keys: Default::default(),
+            };
+            let idms_prox_read = idms.proxy_read().await.unwrap();
+            idms_prox_read
+                .check_oauth2_authorisation(Some(ident), &auth_req, ct)
+                .map(|_| ())
+        };
+
+        let assign = |members: Vec<Uuid>| async move {
+            let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+            let modlist = ModifyList::new_list(
+                std::iter::once(Modify::Purged(Attribute::OAuth2RsAssignedMember))
+                    .chain(members.into_iter().map(|u| {
+                        Modify::Present(Attribute::OAuth2RsAssignedMember, Value::Refer(u))
+                    }))
+                    .collect(),
+            );
+            assert!(idms_prox_write
+                .qs_write
+                .internal_modify_uuid(rs_uuid, &modlist)
+                .is_ok());
+            assert!(idms_prox_write.commit().is_ok());
+        };
+
+        // Without assignments, anyone with a scope map may authorise.
+        assert!(authorise().await.is_ok());
+
+        // Assigned to a group the account is not a member of.
+        assign(vec![UUID_IDM_ADMINS]).await;
+        assert_eq!(authorise().await, Err(Oauth2Error::ClientNotAssigned));
+
+        // Assigned to a group the account is a member of.
+        assign(vec![UUID_IDM_ADMINS, UUID_TESTGROUP]).await;
+        assert!(authorise().await.is_ok());
+
+        // Assigned to the account directly.
+        assign(vec![UUID_TESTPERSON_1]).await;
+        assert!(authorise().await.is_ok());
+
+        // Removing all assignments makes the client available again.
+        assign(Vec::new()).await;
+        assert!(authorise().await.is_ok());
+    }
 }
@@ -0,0 +1,215 @@
+//! Persons may review the consents they have granted to OAuth2 clients, and revoke them. When
+//! a consent is revoked, the sessions of the client are also ended, and the person is asked
+//! to consent again the next time the client requests access.
+
+use kanidm_proto::internal::Oauth2Consent as ProtoOauth2Consent;
+
+use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction};
+use crate::prelude::*;
+use crate::value::SessionState;
+
+pub struct RevokeSelfOauth2ConsentEvent {
+    // Who initiated this? The consent must belong to this person.
+    pub ident: Identity,
+    // The name of the client.
+    pub client_name: String,
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub fn list_self_oauth2_consents(
+        &mut self,
+        ident: &Identity,
+    ) -> Result<Vec<ProtoOauth2Consent>, OperationError> {
+        let Some(target) = ident.get_uuid() else {
+            error!("Only persons may list their own oauth2 consents");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let entry = self.qs_read.internal_search_uuid(target)?;
+
+        let Some(consents) = entry.get_ava_as_oauthscopemaps(Attribute::OAuth2ConsentScopeMap)
+        else {
+            return Ok(Vec::with_capacity(0));
+        };
+
+        let mut consents = consents
+            .iter()
+            .filter_map(|(client_uuid, scopes)| {
+                // The client may have been deleted since the consent was granted.
+                let client = self.qs_read.internal_search_uuid(*client_uuid).ok()?;
+
+                let client_name = client
+                    .get_ava_single_iname(Attribute::Name)
+                    .map(str::to_string)?;
+                let display_name = client
+                    .get_ava_single_utf8(Attribute::DisplayName)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| client_name.clone());
+
+                Some(ProtoOauth2Consent {
+                    client_name,
+                    display_name,
+                    scopes: scopes.iter().cloned().collect(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        consents.sort_unstable_by(|a, b| a.display_name.cmp(&b.display_name));
+
+        Ok(consents)
+    }
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub fn revoke_self_oauth2_consent(
+        &mut self,
+        rce: &RevokeSelfOauth2ConsentEvent,
+    ) -> Result<(), OperationError> {
+        let Some(target) = rce.ident.get_uuid() else {
+            error!("Only persons may revoke their own oauth2 consents");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let client_uuid = self.qs_write.name_to_uuid(&rce.client_name)?;
+
+        let entry = self.qs_write.internal_search_uuid(target)?;
+
+        let consent_exists = entry
+            .get_ava_as_oauthscopemaps(Attribute::OAuth2ConsentScopeMap)
+            .map(|consents| consents.contains_key(&client_uuid))
+            .unwrap_or(false);
+
+        if !consent_exists {
+            error!(client_name = %rce.client_name, "No consent has been granted to this client");
+            return Err(OperationError::NoMatchingEntries);
+        }
+
+        // End the sessions of the client too, so that it can't continue to act on the
+        // consent that was revoked.
+        let mut modlist = entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .into_iter()
+            .flatten()
+            .filter(|(_, session)| {
+                session.rs_uuid == client_uuid
+                    && !matches!(session.state, SessionState::RevokedAt(_))
+            })
+            .map(|(session_id, _)| {
+                Modify::Removed(Attribute::OAuth2Session, PartialValue::Refer(*session_id))
+            })
+            .collect::<Vec<_>>();
+
+        modlist.push(Modify::Removed(
+            Attribute::OAuth2ConsentScopeMap,
+            PartialValue::Refer(client_uuid),
+        ));
+
+        // Revoking a consent only ever reduces access, so this is permitted regardless of
+        // the access controls on the person.
+        self.qs_write
+            .internal_modify_uuid(target, &ModifyList::new_list(modlist))
+            .map_err(|err| {
+                error!(?err, "Failed to revoke oauth2 consent");
+                err
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::RevokeSelfOauth2ConsentEvent;
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[idm_test]
+    async fn test_idm_self_oauth2_consent(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let target_uuid = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+
+        let e_rs = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(rs_uuid)),
+            (Attribute::Name, Value::new_iname("test_resource_server")),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("Test Resource Server")
+            ),
+            (
+                Attribute::OAuth2RsOrigin,
+                Value::new_url_s("https://demo.example.com").unwrap()
+            )
+        );
+
+        let e_usr = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Uuid, Value::Uuid(target_uuid)),
+            (Attribute::Name, Value::new_iname("kevin")),
+            (Attribute::DisplayName, Value::new_utf8s("Kevin")),
+            (
+                Attribute::OAuth2ConsentScopeMap,
+                Value::OauthScopeMap(rs_uuid, btreeset!["openid".to_string()])
+            )
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![e_rs, e_usr])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let entry = idms_prox_read
+            .qs_read
+            .internal_search_uuid(target_uuid)
+            .expect("Unable to find person");
+        let ident = Identity::from_impersonate_entry_readonly(entry);
+
+        let consents = idms_prox_read
+            .list_self_oauth2_consents(&ident)
+            .expect("Unable to list consents");
+        assert_eq!(consents.len(), 1);
+        assert_eq!(consents[0].client_name, "test_resource_server");
+        assert_eq!(consents[0].display_name, "Test Resource Server");
+        assert_eq!(consents[0].scopes, vec!["openid".to_string()]);
+        drop(idms_prox_read);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let rce = RevokeSelfOauth2ConsentEvent {
+            ident: ident.clone(),
+            client_name: "test_resource_server".to_string(),
+        };
+        assert!(idms_prox_write.revoke_self_oauth2_consent(&rce).is_ok());
+
+        // Once revoked, it can't be revoked again.
+        assert_eq!(
+            idms_prox_write.revoke_self_oauth2_consent(&rce),
+            Err(OperationError::NoMatchingEntries)
+        );
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let consents = idms_prox_read
+            .list_self_oauth2_consents(&ident)
+            .expect("Unable to list consents");
+        assert!(consents.is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+//! Upstream OpenID Connect providers allow people to login to Kanidm with an account they
+//! hold at another identity provider, such as Azure AD. Kanidm acts as a broker - the person
+//! still names the Kanidm account they are logging in to, and is redirected to the provider
+//! to authenticate.
+//!
+//! When they return, the code is exchanged for an id token. The token must be signed by the
+//! provider, be issued to us for this authentication session, and contain a mail address of
+//! the account that the person is logging in to. Only then is a session issued.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use compact_jwt::compact::JwkKeySet;
+use compact_jwt::crypto::JwsRs256Verifier;
+use compact_jwt::{JwsEs256Verifier, JwsVerifier, OidcToken, OidcUnverified};
+use kanidm_proto::v1::FederatedProvider;
+use openssl::sha;
+
+use crate::prelude::*;
+use crate::utils::password_from_random;
+
+/// The path that providers return to after the person has authenticated.
+pub const OIDC_UPSTREAM_CALLBACK_PATH: &str = "/ui/login/federated/callback";
+
+/// The configuration of an upstream provider, loaded from an `oidc_upstream` entry.
+#[derive(Clone)]
+pub struct OidcUpstream {
+    pub uuid: Uuid,
+    pub name: String,
+    pub displayname: String,
+    pub issuer: Url,
+    pub client_id: String,
+    client_secret: String,
+    pub authorisation_endpoint: Url,
+    pub token_endpoint: Url,
+    pub jwks_uri: Url,
+}
+
+impl fmt::Debug for OidcUpstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The client secret must not be logged.
+        f.debug_struct("OidcUpstream")
+            .field("uuid", &self.uuid)
+            .field("name", &self.name)
+            .field("issuer", &self.issuer)
+            .field("client_id", &self.client_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OidcUpstream {
+    fn try_from_entry(entry: &EntrySealedCommitted) -> Result<Self, OperationError> {
+        let missing = |attr: Attribute| {
+            error!(uuid = %entry.get_uuid(), %attr, "oidc upstream is missing an attribute");
+            OperationError::MissingAttribute(attr)
+        };
+
+        Ok(OidcUpstream {
+            uuid: entry.get_uuid(),
+            name: entry
+                .get_ava_single_iname(Attribute::Name)
+                .ok_or_else(|| missing(Attribute::Name))?
+                .to_string(),
+            displayname: entry
+                .get_ava_single_utf8(Attribute::DisplayName)
+                .ok_or_else(|| missing(Attribute::DisplayName))?
+                .to_string(),
+            issuer: entry
+                .get_ava_single_url(Attribute::OidcUpstreamIssuer)
+                .ok_or_else(|| missing(Attribute::OidcUpstreamIssuer))?
+                .clone(),
+            client_id: entry
+                .get_ava_single_utf8(Attribute::OidcUpstreamClientId)
+                .ok_or_else(|| missing(Attribute::OidcUpstreamClientId))?
+                .to_string(),
+            client_secret: entry
+                .get_ava_single_secret(Attribute::OidcUpstreamClientSecret)
+                .ok_or_else(|| missing(Attribute::OidcUpstreamClientSecret))?
+                .to_string(),
+            authorisation_endpoint: entry
+                .get_ava_single_url(Attribute::OidcUpstreamAuthorisationEndpoint)
+                .ok_or_else(|| missing(Attribute::OidcUpstreamAuthorisationEndpoint))?
+                .clone(),
+            token_endpoint: entry
+                .get_ava_single_url(Attribute::OidcUpstreamTokenEndpoint)
+                .ok_or_else(|| missing(Attribute::OidcUpstreamTokenEndpoint))?
+                .clone(),
+            jwks_uri: entry
+                .get_ava_single_url(Attribute::OidcUpstreamJwksUri)
+                .ok_or_else(|| missing(Attribute::OidcUpstreamJwksUri))?
+                .clone(),
+        })
+    }
+}
+
+/// Load the upstream providers that people may login with.
+pub(crate) fn oidc_upstreams_load(
+    qs: &mut QueryServerReadTransaction<'_>,
+) -> Result<Vec<OidcUpstream>, OperationError> {
+    qs.internal_search(filter!(f_eq(
+        Attribute::Class,
+        EntryClass::OidcUpstream.into()
+    )))?
+    .iter()
+    .map(|entry| OidcUpstream::try_from_entry(entry))
+    .collect()
+}
+
+/// What is needed to exchange the code from a provider for an id token. The exchange is
+/// made by the caller, outside of any transaction.
+#[derive(Clone)]
+pub struct OidcUpstreamExchange {
+    pub token_endpoint: Url,
+    pub jwks_uri: Url,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: Url,
+    pub code_verifier: String,
+}
+
+/// A login with a single provider, as offered to the person during an authentication session.
+#[derive(Clone, Debug)]
+pub(crate) struct OidcUpstreamRequest {
+    upstream: OidcUpstream,
+    state: String,
+    nonce: String,
+    code_verifier: String,
+}
+
+impl OidcUpstreamRequest {
+    pub(crate) fn new(upstream: OidcUpstream) -> Self {
+        OidcUpstreamRequest {
+            upstream,
+            state: password_from_random(),
+            nonce: password_from_random(),
+            code_verifier: password_from_random(),
+        }
+    }
+
+    pub(crate) fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// The provider as shown to the person, with where to send them to login.
+    pub(crate) fn provider(&self, redirect_uri: &Url) -> FederatedProvider {
+        let code_challenge = URL_SAFE_NO_PAD.encode(sha::sha256(self.code_verifier.as_bytes()));
+
+        let mut authorise_url = self.upstream.authorisation_endpoint.clone();
+        authorise_url
+            .query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.upstream.client_id)
+            .append_pair("redirect_uri", redirect_uri.as_str())
+            .append_pair("scope", "openid email profile")
+            .append_pair("state", &self.state)
+            .append_pair("nonce", &self.nonce)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        FederatedProvider {
+            name: self.upstream.name.clone(),
+            displayname: self.upstream.displayname.clone(),
+            authorise_url,
+        }
+    }
+
+    pub(crate) fn exchange(&self, redirect_uri: &Url) -> OidcUpstreamExchange {
+        OidcUpstreamExchange {
+            token_endpoint: self.upstream.token_endpoint.clone(),
+            jwks_uri: self.upstream.jwks_uri.clone(),
+            client_id: self.upstream.client_id.clone(),
+            client_secret: self.upstream.client_secret.clone(),
+            redirect_uri: redirect_uri.clone(),
+            code_verifier: self.code_verifier.clone(),
+        }
+    }
+
+    /// Verify the id token from the provider, and that it identifies the person by one of
+    /// the mail addresses of the account.
+    pub(crate) fn verify(
+        &self,
+        id_token: &str,
+        jwks: &JwkKeySet,
+        mails: &[String],
+        ct: Duration,
+    ) -> Result<(), &'static str> {
+        let unverified = OidcUnverified::from_str(id_token).map_err(|err| {
+            security_error!(?err, "unable to parse id token from upstream");
+            "invalid id token"
+        })?;
+
+        let token: OidcToken = jwks
+            .keys
+            .iter()
+            .find_map(|jwk| {
+                // Providers may sign with either of these, and the key type decides which.
+                JwsEs256Verifier::try_from(jwk)
+                    .and_then(|verifier| verifier.verify(&unverified))
+                    .or_else(|_| {
+                        JwsRs256Verifier::try_from(jwk)
+                            .and_then(|verifier| verifier.verify(&unverified))
+                    })
+                    .ok()
+            })
+            .ok_or_else(|| {
+                security_error!(upstream = %self.upstream.name, "id token signature is invalid");
+                "invalid id token signature"
+            })?
+            .verify_exp(ct.as_secs() as i64)
+            .map_err(|err| {
+                security_error!(?err, "id token from upstream has expired");
+                "expired id token"
+            })?;
+
+        if token.iss != self.upstream.issuer {
+            security_error!(iss = %token.iss, "id token issuer does not match upstream");
+            return Err("id token issuer does not match");
+        }
+
+        if token.aud != self.upstream.client_id {
+            security_error!(aud = %token.aud, "id token was not issued to this client");
+            return Err("id token audience does not match");
+        }
+
+        if token.nonce.as_deref() != Some(self.nonce.as_str()) {
+            security_error!("id token nonce does not match this authentication session");
+            return Err("id token nonce does not match");
+        }
+
+        if token.s_claims.email_verified == Some(false) {
+            security_error!("upstream has not verified the mail address in the id token");
+            return Err("mail address is not verified");
+        }
+
+        let Some(email) = token.s_claims.email.as_deref() else {
+            security_error!("id token does not contain a mail address");
+            return Err("id token has no mail address");
+        };
+
+        if mails.iter().any(|mail| mail.eq_ignore_ascii_case(email)) {
+            security_info!(upstream = %self.upstream.name, "id token matches account");
+            Ok(())
+        } else {
+            security_error!(%email, "id token mail address does not belong to this account");
+            Err("mail address does not belong to this account")
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! When account policy sets a maximum password age, authenticating with an expired password
+//! doesn't issue a session. Instead the client is given a short lived token, signed by the
+//! domain key, that is exchanged for a credential update session so that the password can be
+//! changed before authenticating again.
+//!
+//! The token can only be exchanged while the password remains expired, so once it has been
+//! changed the token is of no further use.
+
+use std::time::Duration;
+
+use compact_jwt::{Jws, JwsCompact};
+use serde::{Deserialize, Serialize};
+
+use crate::idm::account::Account;
+use crate::idm::credupdatesession::{
+    CredentialUpdateSessionStatus, CredentialUpdateSessionToken, InitCredentialUpdateEvent,
+};
+use crate::idm::server::IdmServerProxyWriteTransaction;
+use crate::prelude::*;
+use crate::server::keys::KeyObject;
+
+/// How long the token to change an expired password is valid for, in seconds.
+pub const PASSWORD_EXPIRED_TOKEN_EXPIRY: u64 = 300;
+
+const PASSWORD_EXPIRED_PURPOSE: &str = "password_expired";
+
+#[derive(Serialize, Deserialize)]
+struct PasswordExpiredClaims {
+    sub: Uuid,
+    nonce: Uuid,
+    exp: i64,
+    // Distinguishes this from other tokens signed by the domain key with the same claims.
+    purpose: String,
+}
+
+/// Sign a token that allows the account to change its expired password.
+pub(crate) fn password_expired_token_issue(
+    account: &Account,
+    key_object: &KeyObject,
+    ct: Duration,
+) -> Result<JwsCompact, OperationError> {
+    let expiry = ct + Duration::from_secs(PASSWORD_EXPIRED_TOKEN_EXPIRY);
+
+    let claims = PasswordExpiredClaims {
+        sub: account.uuid,
+        nonce: Uuid::new_v4(),
+        exp: expiry.as_secs() as i64,
+        purpose: PASSWORD_EXPIRED_PURPOSE.to_string(),
+    };
+
+    let jws = Jws::into_json(&claims).map_err(|err| {
+        error!(?err, "Failed to serialise password expired token");
+        OperationError::AU0002JwsSerialisation
+    })?;
+
+    key_object.jws_es256_sign(&jws, ct).map_err(|err| {
+        error!(?err, "Failed to sign password expired token");
+        OperationError::AU0003JwsSignature
+    })
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    /// Exchange the token from an authentication with an expired password for a credential
+    /// update session of the account.
+    #[instrument(level = "debug", skip_all)]
+    pub fn password_expired_exchange(
+        &mut self,
+        token: &JwsCompact,
+        source: Source,
+        ct: Duration,
+    ) -> Result<(CredentialUpdateSessionToken, CredentialUpdateSessionStatus), OperationError> {
+        let claims = self
+            .qs_write
+            .get_domain_key_object_handle()?
+            .jws_verify(token)
+            .map_err(|err| {
+                security_info!(?err, "Unable to verify password expired token");
+                OperationError::NotAuthenticated
+            })?
+            .from_json::<PasswordExpiredClaims>()
+            .map_err(|err| {
+                security_info!(?err, "Token is not a password expired token");
+                OperationError::NotAuthenticated
+            })?;
+
+        if claims.purpose != PASSWORD_EXPIRED_PURPOSE {
+            security_info!("Token is not a password expired token");
+            return Err(OperationError::NotAuthenticated);
+        }
+
+        if claims.exp <= ct.as_secs() as i64 {
+            security_info!("Password expired token has expired");
+            return Err(OperationError::SessionExpired);
+        }
+
+        let entry = self.qs_write.internal_search_uuid(claims.sub)?;
+        let (account, account_policy) =
+            Account::try_from_entry_with_policy(entry.as_ref(), &mut self.qs_write)?;
+
+        if !account.is_within_valid_time(ct) {
+            security_info!(%account.uuid, "Password expired token denied - account is not valid");
+            return Err(OperationError::SessionExpired);
+        }
+
+        // Once the password has been changed the token can't be used again.
+        let still_expired = account_policy
+            .pw_max_age()
+            .is_some_and(|max_age| account.password_expired(max_age, ct));
+        if !still_expired {
+            security_info!(%account.uuid, "Password expired token denied - password is not expired");
+            return Err(OperationError::SessionExpired);
+        }
+
+        // The person has just authenticated with their credentials, so they may update them as
+        // they would with a privileged session of their own.
+        let ident = Identity::from_password_expired(entry, source);
+        let init_event = InitCredentialUpdateEvent::new(ident, account.uuid);
+        let result = self.init_credential_update(&init_event, ct)?;
+
+        security_info!(%account.uuid, "Began credential update for an expired password");
+
+        Ok(result)
+    }
+}
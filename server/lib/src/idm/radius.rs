@@ -1,11 +1,17 @@
 use std::time::Duration;
 
+use kanidm_lib_crypto::mtls::{build_self_signed_client_ca, sign_client_certificate_request};
+use kanidm_lib_crypto::prelude::{PKey, Private, X509};
 use kanidm_proto::internal::RadiusAuthToken;
+use openssl::x509::X509Req;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+use crate::be::keystorage::{KeyHandle, KeyHandleId};
 use crate::entry::{Entry, EntryCommitted, EntryReduced};
+use crate::idm::event::RadiusCertificateIssueEvent;
 use crate::idm::group::Group;
+use crate::idm::server::IdmServerProxyWriteTransaction;
 use crate::prelude::*;
 
 #[derive(Debug, Clone)]
@@ -15,6 +21,7 @@ pub(crate) struct RadiusAccount {
     pub uuid: Uuid,
     pub groups: Vec<Group<()>>,
     pub radius_secret: String,
+    pub certificate_serials: Vec<String>,
     pub valid_from: Option<OffsetDateTime>,
     pub expire: Option<OffsetDateTime>,
 }
@@ -51,12 +58,35 @@ impl RadiusAccount {
 
         let expire = value.get_ava_single_datetime(Attribute::AccountExpire);
 
+        // The serials of the client certificates that belong to this account. Removing the
+        // certificate entry revokes the certificate for EAP-TLS.
+        let certificate_serials = qs
+            .internal_search(filter!(f_and!([
+                f_eq(Attribute::Class, EntryClass::ClientCertificate.into()),
+                f_eq(Attribute::Refers, PartialValue::Refer(uuid))
+            ])))?
+            .iter()
+            .filter_map(|cert_entry| {
+                cert_entry
+                    .get_ava_set(Attribute::Certificate)
+                    .and_then(|vs| vs.as_certificate_set())
+            })
+            .flat_map(|certs| certs.values())
+            .map(|cert| {
+                // Serials are presented by the radius server without leading zeros.
+                let serial = cert.tbs_certificate.serial_number.as_bytes();
+                let start = serial.iter().position(|b| *b != 0).unwrap_or(serial.len());
+                hex::encode(&serial[start..])
+            })
+            .collect();
+
         Ok(RadiusAccount {
             name,
             displayname,
             uuid,
             groups,
             radius_secret,
+            certificate_serials,
             valid_from,
             expire,
         })
@@ -101,6 +131,140 @@ impl RadiusAccount {
             uuid: self.uuid.as_hyphenated().to_string(),
             secret: self.radius_secret.clone(),
             groups: self.groups.iter().map(|g| g.to_proto()).collect(),
+            certificate_serials: self.certificate_serials.clone(),
         })
     }
 }
+
+impl IdmServerProxyWriteTransaction<'_> {
+    fn radius_generate_ca(&mut self) -> Result<(PKey<Private>, X509), OperationError> {
+        let s_uuid = self.qs_write.get_server_uuid();
+        let domain_name = self.qs_write.get_domain_name().to_string();
+
+        let (private, x509) =
+            build_self_signed_client_ca(s_uuid, &domain_name, RADIUS_CA_CERTIFICATE_DAYS)
+                .map_err(|err| {
+                    error!(?err, "Unable to generate radius certificate authority");
+                    OperationError::CryptographyError
+                })?;
+
+        let kh = KeyHandle::X509Key {
+            private: private.clone(),
+            x509: x509.clone(),
+        };
+
+        self.qs_write
+            .get_be_txn()
+            .set_key_handle(KeyHandleId::RadiusCertificateAuthority, kh)
+            .map_err(|err| {
+                error!(?err, "Unable to persist radius certificate authority");
+                err
+            })
+            .map(|()| (private, x509))
+    }
+
+    /// Retrieve the certificate authority that issues RADIUS client certificates, creating
+    /// it if it does not exist. This authority is local to this server and is not replicated.
+    fn radius_get_ca(&mut self) -> Result<(PKey<Private>, X509), OperationError> {
+        let maybe_key_handle = self
+            .qs_write
+            .get_be_txn()
+            .get_key_handle(KeyHandleId::RadiusCertificateAuthority)
+            .map_err(|err| {
+                error!(?err, "Unable to access radius certificate authority");
+                err
+            })?;
+
+        match maybe_key_handle {
+            Some(KeyHandle::X509Key { private, x509 }) => Ok((private, x509)),
+            Some(_) => {
+                error!("Radius certificate authority key handle contains an unexpected key type");
+                Err(OperationError::InvalidState)
+            }
+            None => self.radius_generate_ca(),
+        }
+    }
+
+    /// The PEM encoded certificate of the authority that issues RADIUS client certificates.
+    /// This must be trusted by the RADIUS server for EAP-TLS.
+    pub fn radius_ca_certificate_pem(&mut self) -> Result<String, OperationError> {
+        let (_, x509) = self.radius_get_ca()?;
+
+        x509.to_pem()
+            .map_err(|err| {
+                error!(?err, "Unable to encode radius certificate authority");
+                OperationError::CryptographyError
+            })
+            .and_then(|pem| String::from_utf8(pem).map_err(|_| OperationError::InvalidState))
+    }
+
+    /// Sign a certificate signing request, issuing a client certificate for EAP-TLS that is
+    /// bound to the target account. The certificate is recorded as a client certificate of
+    /// the account so that it can be revoked.
+    #[instrument(level = "debug", skip_all)]
+    pub fn issue_radius_certificate(
+        &mut self,
+        rcie: &RadiusCertificateIssueEvent,
+    ) -> Result<String, OperationError> {
+        let account = self.target_to_account(rcie.target)?;
+
+        let csr = X509Req::from_pem(rcie.csr.as_bytes()).map_err(|err| {
+            debug!(?err, "Invalid certificate signing request");
+            OperationError::InvalidRequestState
+        })?;
+
+        let (ca_key, ca_cert) = self.radius_get_ca()?;
+
+        let x509 = sign_client_certificate_request(
+            &ca_key,
+            &ca_cert,
+            &csr,
+            account.uuid,
+            &account.spn,
+            RADIUS_CLIENT_CERTIFICATE_DAYS,
+        )
+        .map_err(|err| {
+            debug!(?err, "Unable to sign certificate signing request");
+            OperationError::InvalidRequestState
+        })?;
+
+        let cert_pem = x509
+            .to_pem()
+            .map_err(|err| {
+                error!(?err, "Unable to encode radius client certificate");
+                OperationError::CryptographyError
+            })
+            .and_then(|pem| String::from_utf8(pem).map_err(|_| OperationError::InvalidState))?;
+
+        let certificate =
+            Value::new_certificate_s(&cert_pem).ok_or(OperationError::CryptographyError)?;
+
+        let entry = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::ClientCertificate.to_value()),
+            (Attribute::Certificate, certificate),
+            (Attribute::Refers, Value::Refer(account.uuid))
+        );
+
+        // An account may always issue certificates for itself, otherwise the identity must be
+        // permitted to manage client certificates.
+        let ce = if rcie.ident.get_uuid() == Some(account.uuid)
+            && rcie.ident.access_scope() == AccessScope::ReadWrite
+        {
+            CreateEvent::new_internal(vec![entry])
+        } else {
+            CreateEvent {
+                ident: rcie.ident.clone(),
+                entries: vec![entry],
+            }
+        };
+
+        self.qs_write
+            .create(&ce)
+            .map_err(|e| {
+                request_error!(error = ?e);
+                e
+            })
+            .map(|()| cert_pem)
+    }
+}
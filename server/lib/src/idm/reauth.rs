@@ -2,7 +2,8 @@ use crate::prelude::*;
 
 use crate::credential::softlock::CredSoftLock;
 use crate::idm::account::Account;
-use crate::idm::authsession::{AuthSession, AuthSessionData};
+use crate::idm::authmetrics::AuthFunnelStep;
+use crate::idm::authsession::{AuthSession, AuthSessionData, CREDENTIAL_SOFTLOCKED};
 use crate::idm::event::AuthResult;
 use crate::idm::server::IdmServerAuthTransaction;
 use crate::idm::AuthState;
@@ -88,18 +89,19 @@ impl IdmServerAuthTransaction<'_> {
                 // this re-auth session. Else passkeys/devicekeys are not bounded by this
                 // problem.
                 if cred_uuid == session_cred_id {
+                    let policy = account_policy.softlock_policy(policy);
                     let mut softlock_write = self.softlocks.write();
                     let slock_ref: CredSoftLockMutex =
                         if let Some(slock_ref) = softlock_write.get(&cred_uuid) {
                             slock_ref.clone()
                         } else {
                             // Create if not exist, and the cred type supports softlocking.
-                            let slock = Arc::new(Mutex::new(CredSoftLock::new(policy)));
+                            let slock = Arc::new(Mutex::new(CredSoftLock::new(policy.clone())));
                             softlock_write.insert(cred_uuid, slock.clone());
                             slock
                         };
                     softlock_write.commit();
-                    Some(slock_ref)
+                    Some((slock_ref, policy))
                 } else {
                     None
                 }
@@ -109,8 +111,10 @@ impl IdmServerAuthTransaction<'_> {
         // already selected our credential so we can test it's slock, else we could be allowing
         // 1-attempt per-reauth.
 
-        let is_valid = if let Some(slock_ref) = maybe_slock {
+        let is_valid = if let Some((slock_ref, policy)) = maybe_slock {
             let mut slock = slock_ref.lock().await;
+            // The account policy may have changed since the softlock was created.
+            slock.set_policy(policy);
             slock.apply_time_step(ct);
             slock.is_valid()
         } else {
@@ -124,7 +128,7 @@ impl IdmServerAuthTransaction<'_> {
             );
             return Ok(AuthResult {
                 sessionid: ident.get_session_id(),
-                state: AuthState::Denied("Credential is temporarily locked".to_string()),
+                state: AuthState::Denied(CREDENTIAL_SOFTLOCKED.to_string()),
             });
         }
 
@@ -136,6 +140,10 @@ impl IdmServerAuthTransaction<'_> {
             webauthn: self.webauthn,
             ct,
             client_auth_info,
+            // Email links and upstream logins can't be used to reauthenticate.
+            email_link_tx: None,
+            oidc_upstreams: Vec::new(),
+            unusual_location: false,
         };
 
         let domain_keys = self.qs_read.get_domain_key_object_handle()?;
@@ -143,9 +151,16 @@ impl IdmServerAuthTransaction<'_> {
         let (auth_session, state) =
             AuthSession::new_reauth(asd, ident.session_id, session, session_cred_id, domain_keys);
 
+        self.auth_metrics.record_initiated();
+
         // Push the re-auth session to the session maps.
         match auth_session {
             Some(auth_session) => {
+                // The mechanism is chosen by the credential the session was created with.
+                if let Some(mech) = auth_session.current_mech() {
+                    self.auth_metrics.record(&mech, AuthFunnelStep::Chosen);
+                }
+
                 let mut session_write = self.sessions.write();
                 if session_write.contains_key(&sessionid) {
                     // If we have a session of the same id, return an error (despite how
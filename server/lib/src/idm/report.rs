@@ -0,0 +1,375 @@
+//! Reports summarise the state of the directory for administrators, such as accounts that
+//! haven't been used in some time or credentials that are about to expire. The content of a
+//! report is gathered here, and rendering and delivery is the responsibility of the caller.
+
+use std::str::FromStr;
+
+use kanidm_proto::v1::SshPublicKeyExpiry;
+use time::OffsetDateTime;
+
+use crate::credential::CredentialType;
+use crate::idm::notification::NotificationRecipient;
+use crate::idm::server::IdmServerProxyReadTransaction;
+use crate::prelude::*;
+
+/// The groups whose members are considered privileged in the security posture report.
+const PRIVILEGED_GROUPS: [Uuid; 2] = [UUID_IDM_ADMINS, UUID_SYSTEM_ADMINS];
+
+/// The content of a report. Each row has one value for each column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub title: String,
+    /// Lines that summarise the report, shown before the rows.
+    pub summary: Vec<String>,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Report {
+    pub fn new(title: &str, columns: &[&str]) -> Self {
+        Report {
+            title: title.to_string(),
+            summary: Vec::new(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+}
+
+fn report_time(time: OffsetDateTime) -> String {
+    time.format(&Rfc3339).unwrap_or_else(|_| time.to_string())
+}
+
+fn report_spn(entry: &EntrySealedCommitted) -> String {
+    entry
+        .get_ava_single_proto_string(Attribute::Spn)
+        .unwrap_or_else(|| entry.get_uuid().to_string())
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    /// The members of this group that have a mail address, including members of nested
+    /// groups.
+    pub fn report_recipients(
+        &mut self,
+        group: &str,
+    ) -> Result<Vec<NotificationRecipient>, OperationError> {
+        let group_uuid = self.qs_read.name_to_uuid(group)?;
+
+        let entries = self.qs_read.internal_search(filter!(f_and!([
+            f_eq(Attribute::MemberOf, PartialValue::Refer(group_uuid)),
+            f_pres(Attribute::Mail)
+        ])))?;
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| {
+                let mail = entry.get_ava_mail_primary(Attribute::Mail)?.to_string();
+                let spn = entry.get_ava_single_proto_string(Attribute::Spn)?;
+                let displayname = entry
+                    .get_ava_single_utf8(Attribute::DisplayName)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| spn.clone());
+                Some(NotificationRecipient {
+                    uuid: entry.get_uuid(),
+                    spn,
+                    displayname,
+                    mail,
+                    // Reports are requested by the administrators, so there is nothing to opt
+                    // out of.
+                    opt_out: Default::default(),
+                })
+            })
+            .collect())
+    }
+
+    /// The persons that don't have a multi-factor credential, and so can authenticate with
+    /// only a password or not at all.
+    pub fn security_posture_report(&mut self) -> Result<Report, OperationError> {
+        let entries = self.qs_read.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Person.into()),
+            f_andnot(f_eq(Attribute::Class, EntryClass::Builtin.into()))
+        ])))?;
+
+        let mut report = Report::new("Security posture", &["spn", "credential", "privileged"]);
+
+        let mut privileged_without_mfa = 0;
+
+        for entry in entries.iter() {
+            let has_passkeys = entry
+                .get_ava_passkeys(Attribute::PassKeys)
+                .map(|passkeys| !passkeys.is_empty())
+                .unwrap_or(false);
+
+            let credential = match entry.get_ava_single_credential(Attribute::PrimaryCredential) {
+                _ if has_passkeys => continue,
+                Some(cred) if cred.is_mfa() => continue,
+                Some(cred) => match cred.type_ {
+                    CredentialType::GeneratedPassword(_) => "generated password",
+                    _ => "password",
+                },
+                None => "none",
+            };
+
+            let privileged = entry
+                .get_ava_refer(Attribute::MemberOf)
+                .map(|memberof| PRIVILEGED_GROUPS.iter().any(|g| memberof.contains(g)))
+                .unwrap_or(false);
+
+            if privileged {
+                privileged_without_mfa += 1;
+            }
+
+            report.rows.push(vec![
+                report_spn(entry),
+                credential.to_string(),
+                privileged.to_string(),
+            ]);
+        }
+
+        report.summary.push(format!(
+            "{} of {} persons have no multi-factor credential.",
+            report.rows.len(),
+            entries.len()
+        ));
+        report.summary.push(format!(
+            "{} of these are members of a privileged group.",
+            privileged_without_mfa
+        ));
+
+        Ok(report)
+    }
+
+    /// The accounts that have not authenticated since `since`, including those that have never
+    /// authenticated.
+    pub fn stale_accounts_report(
+        &mut self,
+        since: OffsetDateTime,
+    ) -> Result<Report, OperationError> {
+        let entries = self.qs_read.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Account.into()),
+            f_andnot(f_eq(Attribute::Class, EntryClass::Builtin.into()))
+        ])))?;
+
+        let mut report = Report::new("Stale accounts", &["spn", "last_authenticated"]);
+
+        let mut rows: Vec<(Option<OffsetDateTime>, String)> = entries
+            .iter()
+            .filter_map(|entry| {
+                let last_used = entry
+                    .get_ava_as_credential_usage_map(Attribute::CredentialUsage)
+                    .and_then(|usage| usage.values().map(|u| u.last_used).max());
+
+                match last_used {
+                    Some(last_used) if last_used >= since => None,
+                    _ => Some((last_used, report_spn(entry))),
+                }
+            })
+            .collect();
+
+        // Accounts that have never been used are listed first, then the longest unused.
+        rows.sort();
+
+        report.summary.push(format!(
+            "{} accounts have not authenticated since {}.",
+            rows.len(),
+            report_time(since)
+        ));
+
+        report.rows = rows
+            .into_iter()
+            .map(|(last_used, spn)| {
+                vec![
+                    spn,
+                    last_used
+                        .map(report_time)
+                        .unwrap_or_else(|| "never".to_string()),
+                ]
+            })
+            .collect();
+
+        Ok(report)
+    }
+
+    /// The accounts, api tokens and ssh public keys that expire between `from` and `to`,
+    /// soonest first.
+    pub fn upcoming_expiries_report(
+        &mut self,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> Result<Report, OperationError> {
+        let entries = self.qs_read.internal_search(filter!(f_or!([
+            f_pres(Attribute::AccountExpire),
+            f_pres(Attribute::ApiTokenSession),
+            f_pres(Attribute::SshPublicKeyExpiry)
+        ])))?;
+
+        let within = |expiry: &OffsetDateTime| from <= *expiry && *expiry < to;
+
+        let mut expiries: Vec<(OffsetDateTime, &'static str, String, String)> = Vec::new();
+
+        for entry in entries.iter() {
+            let spn = report_spn(entry);
+
+            if let Some(expiry) = entry
+                .get_ava_single_datetime(Attribute::AccountExpire)
+                .filter(within)
+            {
+                expiries.push((expiry, "account", spn.clone(), String::new()));
+            }
+
+            if let Some(tokens) = entry.get_ava_as_apitoken_map(Attribute::ApiTokenSession) {
+                expiries.extend(tokens.values().filter_map(|token| {
+                    token
+                        .expiry
+                        .filter(within)
+                        .map(|expiry| (expiry, "api token", spn.clone(), token.label.clone()))
+                }));
+            }
+
+            if let Some(keys) = entry
+                .get_ava_set(Attribute::SshPublicKeyExpiry)
+                .and_then(|vs| vs.as_utf8_iter())
+            {
+                expiries.extend(
+                    keys.filter_map(|key| SshPublicKeyExpiry::from_str(key).ok())
+                        .filter(|key| within(&key.expiry))
+                        .map(|key| (key.expiry, "ssh public key", spn.clone(), key.label)),
+                );
+            }
+        }
+
+        expiries.sort();
+
+        let mut report = Report::new("Upcoming expiries", &["expiry", "kind", "spn", "label"]);
+
+        report.summary.push(format!(
+            "{} items expire between {} and {}.",
+            expiries.len(),
+            report_time(from),
+            report_time(to)
+        ));
+
+        report.rows = expiries
+            .into_iter()
+            .map(|(expiry, kind, spn, label)| {
+                vec![report_time(expiry), kind.to_string(), spn, label]
+            })
+            .collect();
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use crate::prelude::*;
+    use crate::value::{CredentialFactor, CredentialUsage};
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[idm_test]
+    async fn test_idm_report_content(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+        let day = time::Duration::days(1);
+
+        let active_uuid = Uuid::new_v4();
+        let stale_uuid = Uuid::new_v4();
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let active = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("active")),
+            (Attribute::Uuid, Value::Uuid(active_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("Active")),
+            (
+                Attribute::Mail,
+                Value::EmailAddress("active@example.com".to_string(), true)
+            ),
+            (
+                Attribute::CredentialUsage,
+                Value::CredentialUsage(
+                    Uuid::new_v4(),
+                    CredentialFactor::Password,
+                    CredentialUsage {
+                        last_used: now,
+                        use_count: 1,
+                        source: None,
+                        user_agent: None,
+                    }
+                )
+            )
+        );
+
+        let stale = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("stale")),
+            (Attribute::Uuid, Value::Uuid(stale_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("Stale")),
+            (
+                Attribute::AccountExpire,
+                Value::new_datetime_epoch(ct + Duration::from_secs(86400 * 3))
+            )
+        );
+
+        let group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("report_recipients")),
+            (Attribute::Member, Value::Refer(active_uuid)),
+            (Attribute::Member, Value::Refer(stale_uuid))
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![active, stale, group])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        // Only members with a mail address receive the report.
+        let recipients = idms_prox_read
+            .report_recipients("report_recipients")
+            .expect("Failed to find report recipients");
+        assert_eq!(recipients.len(), 1);
+        assert_eq!(recipients[0].uuid, active_uuid);
+
+        let report = idms_prox_read
+            .stale_accounts_report(now - day)
+            .expect("Failed to generate stale accounts report");
+        assert!(report
+            .rows
+            .iter()
+            .any(|row| row[0].starts_with("stale@") && row[1] == "never"));
+        assert!(!report.rows.iter().any(|row| row[0].starts_with("active@")));
+
+        // Neither person has a credential.
+        let report = idms_prox_read
+            .security_posture_report()
+            .expect("Failed to generate security posture report");
+        assert!(report
+            .rows
+            .iter()
+            .any(|row| row[0].starts_with("active@") && row[1] == "none"));
+
+        let report = idms_prox_read
+            .upcoming_expiries_report(now, now + day * 7)
+            .expect("Failed to generate upcoming expiries report");
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0][1], "account");
+        assert!(report.rows[0][2].starts_with("stale@"));
+
+        let report = idms_prox_read
+            .upcoming_expiries_report(now + day * 7, now + day * 14)
+            .expect("Failed to generate upcoming expiries report");
+        assert!(report.rows.is_empty());
+    }
+}
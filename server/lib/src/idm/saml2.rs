@@ -0,0 +1,834 @@
+//! A SAML 2.0 identity provider. Many applications only support SAML for single sign on, so
+//! service providers are registered much like OAuth2 clients - each has an entry that names
+//! the assertion consumer urls it may receive assertions at, and maps groups to the values
+//! of the `groups` attribute of the assertion. Only members of a mapped group may sign in.
+//!
+//! Each service provider has its own key object, so the signing key of one can be rotated
+//! or revoked without affecting the others.
+//!
+//! There is no XML library in this tree, and signing XML requires the exact bytes that the
+//! service provider will canonicalise. The assertion is therefore written directly in the
+//! exclusive canonical form - attributes in order, no self closing elements, and the
+//! namespaces it uses declared on the assertion itself. The digest of the assertion is then
+//! the digest of the text that was written.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use openssl::sha;
+use time::OffsetDateTime;
+
+use crate::idm::account::Account;
+use crate::idm::server::IdmServerProxyReadTransaction;
+use crate::prelude::*;
+use crate::server::keys::KeyProvidersTransaction;
+
+/// How long an assertion may be used for after it is issued.
+const SAML2_ASSERTION_VALIDITY: Duration = Duration::from_secs(300);
+
+const SAML2_NS_ASSERTION: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const SAML2_NS_PROTOCOL: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const SAML2_NS_METADATA: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+const XMLDSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+
+const SAML2_BINDING_HTTP_REDIRECT: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+const SAML2_BINDING_HTTP_POST: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+const SAML2_NAMEID_FORMAT_UNSPECIFIED: &str =
+    "urn:oasis:names:tc:SAML:1.1:nameid-format:unspecified";
+const SAML2_ATTRNAME_FORMAT_BASIC: &str = "urn:oasis:names:tc:SAML:2.0:attrname-format:basic";
+const SAML2_STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+
+const XMLDSIG_EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const XMLDSIG_ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const XMLDSIG_RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const XMLDSIG_SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+
+/// A service provider, loaded from a `saml2_service_provider` entry.
+#[derive(Debug, Clone)]
+pub(crate) struct Saml2ServiceProvider {
+    uuid: Uuid,
+    name: String,
+    displayname: String,
+    entity_id: String,
+    acs_urls: Vec<Url>,
+    group_map: BTreeMap<Uuid, BTreeSet<String>>,
+}
+
+impl Saml2ServiceProvider {
+    fn try_from_entry(entry: &EntrySealedCommitted) -> Result<Self, OperationError> {
+        let missing = |attr: Attribute| {
+            error!(uuid = %entry.get_uuid(), %attr, "saml2 service provider is missing an attribute");
+            OperationError::MissingAttribute(attr)
+        };
+
+        Ok(Saml2ServiceProvider {
+            uuid: entry.get_uuid(),
+            name: entry
+                .get_ava_single_iname(Attribute::Name)
+                .ok_or_else(|| missing(Attribute::Name))?
+                .to_string(),
+            displayname: entry
+                .get_ava_single_utf8(Attribute::DisplayName)
+                .ok_or_else(|| missing(Attribute::DisplayName))?
+                .to_string(),
+            entity_id: entry
+                .get_ava_single_utf8(Attribute::Saml2EntityId)
+                .ok_or_else(|| missing(Attribute::Saml2EntityId))?
+                .to_string(),
+            acs_urls: entry
+                .get_ava_set(Attribute::Saml2AcsUrl)
+                .and_then(|vs| vs.as_url_set())
+                .map(|urls| urls.iter().cloned().collect())
+                .ok_or_else(|| missing(Attribute::Saml2AcsUrl))?,
+            group_map: entry
+                .get_ava_as_oauthscopemaps(Attribute::Saml2GroupMap)
+                .cloned()
+                .unwrap_or_default(),
+        })
+    }
+
+    /// The entity id of the identity provider, as seen by this service provider.
+    fn idp_entity_id(&self, origin: &Url) -> Url {
+        let mut entity_id = origin.clone();
+        entity_id.set_path(&format!("/saml2/{}", self.name));
+        entity_id
+    }
+
+    fn sso_url(&self, origin: &Url) -> Url {
+        let mut sso_url = origin.clone();
+        sso_url.set_path(&format!("/ui/saml2/{}/sso", self.name));
+        sso_url
+    }
+}
+
+fn saml2_service_provider_load(
+    qs: &mut QueryServerReadTransaction<'_>,
+    sp_name: &str,
+) -> Result<Saml2ServiceProvider, OperationError> {
+    let entry = qs
+        .internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::Saml2ServiceProvider.into()),
+            f_eq(Attribute::Name, PartialValue::new_iname(sp_name))
+        ])))?
+        .pop()
+        .ok_or_else(|| {
+            admin_warn!(%sp_name, "Invalid SAML service provider. Have you configured it?");
+            OperationError::NoMatchingEntries
+        })?;
+
+    Saml2ServiceProvider::try_from_entry(&entry)
+}
+
+/// The parts of an `AuthnRequest` that decide where the assertion is sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Saml2AuthnRequest {
+    id: String,
+    issuer: Option<String>,
+    acs_url: Option<String>,
+}
+
+struct XmlStartTag<'a> {
+    local_name: &'a str,
+    attrs: Vec<(&'a str, String)>,
+    end: usize,
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Find the next start tag at or after `from`, skipping declarations, comments and end tags.
+fn xml_next_start_tag(xml: &str, mut from: usize) -> Result<Option<XmlStartTag<'_>>, ()> {
+    loop {
+        let Some(offset) = xml.get(from..).and_then(|rest| rest.find('<')) else {
+            return Ok(None);
+        };
+        let start = from + offset + 1;
+        let rest = &xml[start..];
+
+        if rest.starts_with("!DOCTYPE") {
+            // Entities are never expanded, so a document type can only be an attack.
+            return Err(());
+        } else if rest.starts_with("!--") {
+            from = start + rest.find("-->").ok_or(())? + 3;
+            continue;
+        } else if rest.starts_with('?') || rest.starts_with('/') || rest.starts_with('!') {
+            from = start + rest.find('>').ok_or(())? + 1;
+            continue;
+        }
+
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .ok_or(())?;
+        let name = &rest[..name_len];
+        let local_name = name.rsplit(':').next().unwrap_or(name);
+
+        let mut attrs = Vec::new();
+        let mut pos = start + name_len;
+        loop {
+            let rest = xml[pos..].trim_start();
+
+            if let Some(stripped) = rest.strip_prefix("/>").or_else(|| rest.strip_prefix('>')) {
+                let end = xml.len() - stripped.len();
+                return Ok(Some(XmlStartTag {
+                    local_name,
+                    attrs,
+                    end,
+                }));
+            }
+
+            let eq = rest.find('=').ok_or(())?;
+            let key = rest[..eq].trim();
+            let value = rest[eq + 1..].trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')
+                .ok_or(())?;
+            let value = &value[1..];
+            let value_len = value.find(quote).ok_or(())?;
+
+            attrs.push((key, xml_unescape(&value[..value_len])));
+            pos = xml.len() - value.len() + value_len + 1;
+        }
+    }
+}
+
+impl Saml2AuthnRequest {
+    pub(crate) fn parse(xml: &str) -> Result<Self, OperationError> {
+        let invalid = |_| {
+            security_info!("Unable to parse SAML AuthnRequest");
+            OperationError::SA0001AuthnRequestInvalid
+        };
+
+        let root = xml_next_start_tag(xml, 0)
+            .map_err(invalid)?
+            .filter(|tag| tag.local_name == "AuthnRequest")
+            .ok_or(OperationError::SA0001AuthnRequestInvalid)?;
+
+        let attr = |name: &str| {
+            root.attrs
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.clone())
+        };
+
+        let id = attr("ID").ok_or(OperationError::SA0001AuthnRequestInvalid)?;
+        let acs_url = attr("AssertionConsumerServiceURL");
+
+        // The issuer is the first child of the request, if it is present.
+        let issuer = match xml_next_start_tag(xml, root.end).map_err(invalid)? {
+            Some(tag) if tag.local_name == "Issuer" => xml[tag.end..]
+                .split('<')
+                .next()
+                .map(|text| xml_unescape(text.trim())),
+            _ => None,
+        };
+
+        Ok(Saml2AuthnRequest {
+            id,
+            issuer,
+            acs_url,
+        })
+    }
+}
+
+/// Escape text content as exclusive canonicalisation writes it.
+fn c14n_text(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\r', "&#xD;")
+}
+
+/// Escape an attribute value as exclusive canonicalisation writes it.
+fn c14n_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+        .replace('\t', "&#x9;")
+        .replace('\n', "&#xA;")
+        .replace('\r', "&#xD;")
+}
+
+fn saml2_instant(time: Duration) -> Result<String, OperationError> {
+    // Sub-second precision is not needed, and not all service providers accept it.
+    (OffsetDateTime::UNIX_EPOCH + Duration::from_secs(time.as_secs()))
+        .format(&Rfc3339)
+        .map_err(|err| {
+            error!(?err, "Unable to format SAML instant");
+            OperationError::InvalidState
+        })
+}
+
+fn saml2_id() -> String {
+    // Ids must not start with a digit.
+    format!("_{}", Uuid::new_v4().simple())
+}
+
+/// The response to an `AuthnRequest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Saml2SsoResponse {
+    /// The person must authenticate before the request can be answered.
+    AuthenticationRequired { client_name: String },
+    /// The base64 encoded response must be posted to the assertion consumer url.
+    Permitted {
+        acs_url: Url,
+        saml_response: String,
+        relay_state: Option<String>,
+    },
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    /// The metadata of the identity provider for a service provider, that the service provider
+    /// is configured with.
+    pub fn saml2_metadata(&mut self, sp_name: &str) -> Result<String, OperationError> {
+        let sp = saml2_service_provider_load(&mut self.qs_read, sp_name)?;
+        let origin = self.oauth2rs.origin();
+
+        let certificates = self
+            .qs_read
+            .get_key_providers()
+            .get_key_object_handle(sp.uuid)
+            .ok_or(OperationError::KP0060KeyObjectNoActiveSaml2Signing)?
+            .saml2_signing_certificates()?;
+
+        let mut metadata = format!(
+            r#"<md:EntityDescriptor xmlns:md="{}" entityID="{}"><md:IDPSSODescriptor WantAuthnRequestsSigned="false" protocolSupportEnumeration="{}">"#,
+            SAML2_NS_METADATA,
+            c14n_attr(sp.idp_entity_id(origin).as_str()),
+            SAML2_NS_PROTOCOL
+        );
+
+        for certificate in certificates {
+            let der = certificate.to_der().map_err(|err| {
+                error!(?err, "Unable to encode SAML signing certificate");
+                OperationError::KP0059KeyObjectSaml2SigningInvalid
+            })?;
+            metadata.push_str(&format!(
+                r#"<md:KeyDescriptor use="signing"><ds:KeyInfo xmlns:ds="{}"><ds:X509Data><ds:X509Certificate>{}</ds:X509Certificate></ds:X509Data></ds:KeyInfo></md:KeyDescriptor>"#,
+                XMLDSIG_NS,
+                STANDARD.encode(der)
+            ));
+        }
+
+        let sso_url = c14n_attr(sp.sso_url(origin).as_str());
+        metadata.push_str(&format!(
+            r#"<md:NameIDFormat>{}</md:NameIDFormat><md:SingleSignOnService Binding="{}" Location="{}"></md:SingleSignOnService><md:SingleSignOnService Binding="{}" Location="{}"></md:SingleSignOnService></md:IDPSSODescriptor></md:EntityDescriptor>"#,
+            SAML2_NAMEID_FORMAT_UNSPECIFIED,
+            SAML2_BINDING_HTTP_REDIRECT,
+            sso_url,
+            SAML2_BINDING_HTTP_POST,
+            sso_url
+        ));
+
+        Ok(metadata)
+    }
+
+    /// Answer an `AuthnRequest` from a service provider. The request is not signed, so the
+    /// assertion is only ever sent to an assertion consumer url registered for the service
+    /// provider.
+    #[instrument(level = "debug", skip_all)]
+    pub fn check_saml2_sso(
+        &mut self,
+        maybe_ident: Option<&Identity>,
+        sp_name: &str,
+        saml_request: &str,
+        relay_state: Option<String>,
+        ct: Duration,
+    ) -> Result<Saml2SsoResponse, OperationError> {
+        let sp = saml2_service_provider_load(&mut self.qs_read, sp_name)?;
+        let request = Saml2AuthnRequest::parse(saml_request)?;
+
+        if let Some(issuer) = &request.issuer {
+            if *issuer != sp.entity_id {
+                security_info!(%issuer, entity_id = %sp.entity_id, "SAML AuthnRequest issuer does not match the service provider");
+                return Err(OperationError::SA0003AuthnRequestIssuerMismatch);
+            }
+        }
+
+        let acs_url = match &request.acs_url {
+            Some(acs_url) => sp
+                .acs_urls
+                .iter()
+                .find(|url| url.as_str() == acs_url)
+                .cloned()
+                .ok_or_else(|| {
+                    security_info!(%acs_url, "SAML assertion consumer url is not registered");
+                    OperationError::SA0002AcsUrlNotRegistered
+                })?,
+            None => sp
+                .acs_urls
+                .first()
+                .cloned()
+                .ok_or(OperationError::SA0002AcsUrlNotRegistered)?,
+        };
+
+        let Some(ident) = maybe_ident else {
+            debug!("No identity available, assume authentication required");
+            return Ok(Saml2SsoResponse::AuthenticationRequired {
+                client_name: sp.displayname.clone(),
+            });
+        };
+
+        let (Some(account_uuid), Some(entry)) = (ident.get_uuid(), ident.get_user_entry()) else {
+            error!("SAML request ident is not a user, unable to proceed");
+            return Err(OperationError::InvalidState);
+        };
+
+        if account_uuid == UUID_ANONYMOUS {
+            admin_error!(
+                "Invalid SAML request - refusing to allow user that authenticated with anonymous"
+            );
+            return Err(OperationError::AccessDenied);
+        }
+
+        // Only members of a mapped group may sign in.
+        if !sp
+            .group_map
+            .keys()
+            .any(|group_uuid| ident.is_memberof(*group_uuid))
+        {
+            admin_warn!(%ident, sp = %sp.name, "Identity is not a member of a mapped group");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let groups: BTreeSet<&String> = sp
+            .group_map
+            .iter()
+            .filter(|(group_uuid, _)| ident.is_memberof(**group_uuid))
+            .flat_map(|(_, values)| values.iter())
+            .collect();
+
+        let account = Account::try_from_entry_ro(&entry, &mut self.qs_read)?;
+
+        let origin = self.oauth2rs.origin();
+        let idp_entity_id = c14n_text(sp.idp_entity_id(origin).as_str());
+        let issue_instant = saml2_instant(ct)?;
+        let not_on_or_after = saml2_instant(ct + SAML2_ASSERTION_VALIDITY)?;
+        let assertion_id = saml2_id();
+
+        let mut attributes = vec![
+            ("name", vec![account.name.as_str()]),
+            ("spn", vec![account.spn.as_str()]),
+            ("displayname", vec![account.displayname.as_str()]),
+        ];
+        if !account.mail.is_empty() {
+            attributes.push(("mail", account.mail.iter().map(String::as_str).collect()));
+        }
+        if !groups.is_empty() {
+            attributes.push(("groups", groups.iter().map(|g| g.as_str()).collect()));
+        }
+
+        let attribute_statement: String = attributes
+            .into_iter()
+            .map(|(name, values)| {
+                let values: String = values
+                    .into_iter()
+                    .map(|value| {
+                        format!(
+                            "<saml:AttributeValue>{}</saml:AttributeValue>",
+                            c14n_text(value)
+                        )
+                    })
+                    .collect();
+                format!(
+                    r#"<saml:Attribute Name="{}" NameFormat="{}">{}</saml:Attribute>"#,
+                    name, SAML2_ATTRNAME_FORMAT_BASIC, values
+                )
+            })
+            .collect();
+
+        let assertion_start = format!(
+            r#"<saml:Assertion xmlns:saml="{}" ID="{}" IssueInstant="{}" Version="2.0"><saml:Issuer>{}</saml:Issuer>"#,
+            SAML2_NS_ASSERTION, assertion_id, issue_instant, idp_entity_id
+        );
+
+        let assertion_end = format!(
+            concat!(
+                r#"<saml:Subject><saml:NameID Format="{nameid_format}">{account_uuid}</saml:NameID>"#,
+                r#"<saml:SubjectConfirmation Method="urn:oasis:names:tc:SAML:2.0:cm:bearer">"#,
+                r#"<saml:SubjectConfirmationData InResponseTo="{request_id}" NotOnOrAfter="{not_on_or_after}" Recipient="{acs_url}"></saml:SubjectConfirmationData>"#,
+                r#"</saml:SubjectConfirmation></saml:Subject>"#,
+                r#"<saml:Conditions NotBefore="{issue_instant}" NotOnOrAfter="{not_on_or_after}">"#,
+                r#"<saml:AudienceRestriction><saml:Audience>{audience}</saml:Audience></saml:AudienceRestriction>"#,
+                r#"</saml:Conditions>"#,
+                r#"<saml:AuthnStatement AuthnInstant="{issue_instant}" SessionIndex="{assertion_id}">"#,
+                r#"<saml:AuthnContext><saml:AuthnContextClassRef>urn:oasis:names:tc:SAML:2.0:ac:classes:unspecified</saml:AuthnContextClassRef></saml:AuthnContext>"#,
+                r#"</saml:AuthnStatement>"#,
+                r#"<saml:AttributeStatement>{attribute_statement}</saml:AttributeStatement>"#,
+                r#"</saml:Assertion>"#
+            ),
+            nameid_format = SAML2_NAMEID_FORMAT_UNSPECIFIED,
+            account_uuid = account_uuid,
+            request_id = c14n_attr(&request.id),
+            not_on_or_after = not_on_or_after,
+            acs_url = c14n_attr(acs_url.as_str()),
+            issue_instant = issue_instant,
+            audience = c14n_text(&sp.entity_id),
+            assertion_id = assertion_id,
+            attribute_statement = attribute_statement,
+        );
+
+        // The enveloped signature transform removes the signature before the digest is taken.
+        let digest = sha::sha256(format!("{}{}", assertion_start, assertion_end).as_bytes());
+
+        let signed_info = format!(
+            concat!(
+                r#"<ds:SignedInfo xmlns:ds="{xmldsig_ns}">"#,
+                r#"<ds:CanonicalizationMethod Algorithm="{exc_c14n}"></ds:CanonicalizationMethod>"#,
+                r#"<ds:SignatureMethod Algorithm="{rsa_sha256}"></ds:SignatureMethod>"#,
+                r##"<ds:Reference URI="#{assertion_id}"><ds:Transforms>"##,
+                r#"<ds:Transform Algorithm="{enveloped}"></ds:Transform>"#,
+                r#"<ds:Transform Algorithm="{exc_c14n}"></ds:Transform>"#,
+                r#"</ds:Transforms><ds:DigestMethod Algorithm="{sha256}"></ds:DigestMethod>"#,
+                r#"<ds:DigestValue>{digest}</ds:DigestValue></ds:Reference></ds:SignedInfo>"#
+            ),
+            xmldsig_ns = XMLDSIG_NS,
+            exc_c14n = XMLDSIG_EXC_C14N,
+            rsa_sha256 = XMLDSIG_RSA_SHA256,
+            assertion_id = assertion_id,
+            enveloped = XMLDSIG_ENVELOPED_SIGNATURE,
+            sha256 = XMLDSIG_SHA256,
+            digest = STANDARD.encode(digest),
+        );
+
+        let (certificate, signature) = self
+            .qs_read
+            .get_key_providers()
+            .get_key_object_handle(sp.uuid)
+            .ok_or(OperationError::KP0060KeyObjectNoActiveSaml2Signing)?
+            .saml2_sign(signed_info.as_bytes(), ct)?;
+
+        let certificate = certificate.to_der().map_err(|err| {
+            error!(?err, "Unable to encode SAML signing certificate");
+            OperationError::KP0059KeyObjectSaml2SigningInvalid
+        })?;
+
+        let response = format!(
+            concat!(
+                r#"<samlp:Response xmlns:samlp="{protocol_ns}" Destination="{acs_url}" ID="{response_id}" InResponseTo="{request_id}" IssueInstant="{issue_instant}" Version="2.0">"#,
+                r#"<saml:Issuer xmlns:saml="{assertion_ns}">{idp_entity_id}</saml:Issuer>"#,
+                r#"<samlp:Status><samlp:StatusCode Value="{status}"></samlp:StatusCode></samlp:Status>"#,
+                r#"{assertion_start}<ds:Signature xmlns:ds="{xmldsig_ns}">{signed_info}"#,
+                r#"<ds:SignatureValue>{signature}</ds:SignatureValue>"#,
+                r#"<ds:KeyInfo><ds:X509Data><ds:X509Certificate>{certificate}</ds:X509Certificate></ds:X509Data></ds:KeyInfo>"#,
+                r#"</ds:Signature>{assertion_end}</samlp:Response>"#
+            ),
+            protocol_ns = SAML2_NS_PROTOCOL,
+            acs_url = c14n_attr(acs_url.as_str()),
+            response_id = saml2_id(),
+            request_id = c14n_attr(&request.id),
+            issue_instant = issue_instant,
+            assertion_ns = SAML2_NS_ASSERTION,
+            idp_entity_id = idp_entity_id,
+            status = SAML2_STATUS_SUCCESS,
+            assertion_start = assertion_start,
+            xmldsig_ns = XMLDSIG_NS,
+            signed_info = signed_info,
+            signature = STANDARD.encode(signature),
+            certificate = STANDARD.encode(certificate),
+            assertion_end = assertion_end,
+        );
+
+        security_info!(%account_uuid, sp = %sp.name, "Issued SAML assertion");
+
+        Ok(Saml2SsoResponse::Permitted {
+            acs_url,
+            saml_response: STANDARD.encode(response),
+            relay_state,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use kanidm_lib_crypto::prelude::X509;
+    use kanidm_lib_crypto::saml2::saml2_verify;
+    use openssl::sha;
+
+    use super::{Saml2AuthnRequest, Saml2SsoResponse};
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+    const UUID_TESTGROUP: Uuid = uuid!("3e3e3e3e-5d9f-4bb0-96a2-4a01e1c1e6e1");
+    const UUID_TEST_SP: Uuid = uuid!("5a5a5a5a-3b1c-4c0b-8f1a-6b1a2c3d4e5f");
+
+    fn authn_request(acs_url: &str) -> String {
+        format!(
+            r#"<?xml version="1.0"?><samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" ID="_req&amp;1" Version="2.0" AssertionConsumerServiceURL="{}">
+  <!-- A comment <saml:Issuer>wrong</saml:Issuer> -->
+  <saml:Issuer>https://app.example.com/saml</saml:Issuer>
+</samlp:AuthnRequest>"#,
+            acs_url
+        )
+    }
+
+    /// Extract the text between two markers.
+    fn between<'a>(data: &'a str, start: &str, end: &str) -> &'a str {
+        let from = data.find(start).expect("start marker missing") + start.len();
+        let len = data[from..].find(end).expect("end marker missing");
+        &data[from..from + len]
+    }
+
+    #[test]
+    fn test_saml2_authn_request_parse() {
+        let request = Saml2AuthnRequest::parse(&authn_request("https://app.example.com/acs"))
+            .expect("Failed to parse request");
+
+        assert_eq!(request.id, "_req&1");
+        assert_eq!(
+            request.issuer.as_deref(),
+            Some("https://app.example.com/saml")
+        );
+        assert_eq!(
+            request.acs_url.as_deref(),
+            Some("https://app.example.com/acs")
+        );
+
+        assert_eq!(
+            Saml2AuthnRequest::parse(r#"<samlp:LogoutRequest ID="_a"></samlp:LogoutRequest>"#),
+            Err(OperationError::SA0001AuthnRequestInvalid)
+        );
+        assert_eq!(
+            Saml2AuthnRequest::parse(r#"<samlp:AuthnRequest Version="2.0"/>"#),
+            Err(OperationError::SA0001AuthnRequestInvalid)
+        );
+        assert_eq!(
+            Saml2AuthnRequest::parse(
+                r#"<!DOCTYPE x [<!ENTITY a "b">]><samlp:AuthnRequest ID="_a"/>"#
+            ),
+            Err(OperationError::SA0001AuthnRequestInvalid)
+        );
+    }
+
+    #[idm_test]
+    async fn test_idm_saml2_sso(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let e_group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Uuid, Value::Uuid(UUID_TESTGROUP)),
+            (Attribute::Member, Value::Refer(UUID_TESTPERSON_1))
+        );
+
+        let e_sp = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::Saml2ServiceProvider.to_value()
+            ),
+            (Attribute::Class, EntryClass::KeyObject.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::KeyObjectSaml2Signing.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(UUID_TEST_SP)),
+            (Attribute::Name, Value::new_iname("test_sp")),
+            (Attribute::DisplayName, Value::new_utf8s("Test App")),
+            (
+                Attribute::Saml2EntityId,
+                Value::new_utf8s("https://app.example.com/saml")
+            ),
+            (
+                Attribute::Saml2AcsUrl,
+                Value::new_url_s("https://app.example.com/acs").unwrap()
+            ),
+            (
+                Attribute::Saml2GroupMap,
+                Value::new_oauthscopemap(UUID_TESTGROUP, btreeset!["app_users".to_string()])
+                    .expect("invalid group map")
+            )
+        );
+
+        let ce = CreateEvent::new_internal(vec![E_TESTPERSON_1.clone(), e_group, e_sp]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let person = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_TESTPERSON_1)
+            .expect("Failed to load person");
+        let ident = Identity::from_impersonate_entry_readonly(person);
+
+        let request = authn_request("https://app.example.com/acs");
+
+        // Without an identity, the person must authenticate first.
+        let response = idms_prox_read
+            .check_saml2_sso(None, "test_sp", &request, None, ct)
+            .expect("Failed to check request");
+        assert_eq!(
+            response,
+            Saml2SsoResponse::AuthenticationRequired {
+                client_name: "Test App".to_string()
+            }
+        );
+
+        // Assertions are never sent to an unregistered url.
+        assert_eq!(
+            idms_prox_read.check_saml2_sso(
+                Some(&ident),
+                "test_sp",
+                &authn_request("https://evil.example.com/acs"),
+                None,
+                ct
+            ),
+            Err(OperationError::SA0002AcsUrlNotRegistered)
+        );
+
+        let Saml2SsoResponse::Permitted {
+            acs_url,
+            saml_response,
+            relay_state,
+        } = idms_prox_read
+            .check_saml2_sso(
+                Some(&ident),
+                "test_sp",
+                &request,
+                Some("relay".to_string()),
+                ct,
+            )
+            .expect("Failed to check request")
+        else {
+            unreachable!();
+        };
+
+        assert_eq!(acs_url.as_str(), "https://app.example.com/acs");
+        assert_eq!(relay_state.as_deref(), Some("relay"));
+
+        let response = String::from_utf8(STANDARD.decode(saml_response).unwrap()).unwrap();
+        assert!(response.contains(r#"InResponseTo="_req&amp;1""#));
+        assert!(response.contains("<saml:AttributeValue>app_users</saml:AttributeValue>"));
+        assert!(response.contains("<saml:Audience>https://app.example.com/saml</saml:Audience>"));
+
+        // The digest covers the assertion without its signature.
+        let assertion_start = response.find("<saml:Assertion").unwrap();
+        let signature_start = response.find("<ds:Signature ").unwrap();
+        let signature_end = response.find("</ds:Signature>").unwrap() + "</ds:Signature>".len();
+        let assertion_end = response.find("</saml:Assertion>").unwrap() + "</saml:Assertion>".len();
+        let assertion = format!(
+            "{}{}",
+            &response[assertion_start..signature_start],
+            &response[signature_end..assertion_end]
+        );
+        let digest = STANDARD.encode(sha::sha256(assertion.as_bytes()));
+        assert_eq!(
+            between(&response, "<ds:DigestValue>", "</ds:DigestValue>"),
+            digest
+        );
+
+        // And the signature covers the signed info, made by the key in the metadata.
+        let signed_info_start = response.find("<ds:SignedInfo").unwrap();
+        let signed_info_end = response.find("</ds:SignedInfo>").unwrap() + "</ds:SignedInfo>".len();
+        let signed_info = &response[signed_info_start..signed_info_end];
+        let signature = STANDARD
+            .decode(between(
+                &response,
+                "<ds:SignatureValue>",
+                "</ds:SignatureValue>",
+            ))
+            .unwrap();
+
+        let metadata = idms_prox_read
+            .saml2_metadata("test_sp")
+            .expect("Failed to build metadata");
+        let certificate = X509::from_der(
+            &STANDARD
+                .decode(between(
+                    &metadata,
+                    "<ds:X509Certificate>",
+                    "</ds:X509Certificate>",
+                ))
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(saml2_verify(
+            &certificate,
+            signed_info.as_bytes(),
+            &signature
+        ));
+        assert!(metadata.contains("/ui/saml2/test_sp/sso"));
+    }
+
+    #[idm_test]
+    async fn test_idm_saml2_sso_not_mapped(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let e_sp = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::Saml2ServiceProvider.to_value()
+            ),
+            (Attribute::Class, EntryClass::KeyObject.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::KeyObjectSaml2Signing.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(UUID_TEST_SP)),
+            (Attribute::Name, Value::new_iname("test_sp")),
+            (Attribute::DisplayName, Value::new_utf8s("Test App")),
+            (
+                Attribute::Saml2EntityId,
+                Value::new_utf8s("https://app.example.com/saml")
+            ),
+            (
+                Attribute::Saml2AcsUrl,
+                Value::new_url_s("https://app.example.com/acs").unwrap()
+            )
+        );
+
+        let ce = CreateEvent::new_internal(vec![E_TESTPERSON_1.clone(), e_sp]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let person = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_TESTPERSON_1)
+            .expect("Failed to load person");
+        let ident = Identity::from_impersonate_entry_readonly(person);
+
+        // No groups are mapped, so no one may sign in.
+        assert_eq!(
+            idms_prox_read.check_saml2_sso(
+                Some(&ident),
+                "test_sp",
+                &authn_request("https://app.example.com/acs"),
+                None,
+                ct
+            ),
+            Err(OperationError::AccessDenied)
+        );
+
+        // The issuer must be the service provider.
+        assert_eq!(
+            idms_prox_read.check_saml2_sso(
+                Some(&ident),
+                "test_sp",
+                &authn_request("https://app.example.com/acs")
+                    .replace("https://app.example.com/saml", "https://other.example.com"),
+                None,
+                ct
+            ),
+            Err(OperationError::SA0003AuthnRequestIssuerMismatch)
+        );
+    }
+}
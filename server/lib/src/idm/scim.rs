@@ -131,6 +131,7 @@ impl IdmServerProxyWriteTransaction<'_> {
                 // What is the access scope of this session? This is
                 // for auditing purposes.
                 scope,
+                permissions: Vec::with_capacity(0),
             },
         );
 
@@ -205,6 +206,8 @@ impl IdmServerProxyWriteTransaction<'_> {
         &mut self,
         sfe: &ScimSyncFinaliseEvent,
     ) -> Result<(), OperationError> {
+        self.qs_write.check_maintenance_mode(&sfe.ident)?;
+
         // Get the target and ensure it's really a sync account
         let entry = self
             .qs_write
@@ -335,6 +338,8 @@ impl IdmServerProxyWriteTransaction<'_> {
         &mut self,
         ste: &ScimSyncTerminateEvent,
     ) -> Result<(), OperationError> {
+        self.qs_write.check_maintenance_mode(&ste.ident)?;
+
         // Get the target and ensure it's really a sync account
         let entry = self
             .qs_write
@@ -471,6 +476,8 @@ impl IdmServerProxyWriteTransaction<'_> {
         changes: &ScimSyncRequest,
         _ct: Duration,
     ) -> Result<(), OperationError> {
+        self.qs_write.check_maintenance_mode(&sse.ident)?;
+
         let (sync_uuid, sync_authority_set, change_entries, sync_refresh) =
             self.scim_sync_apply_phase_1(sse, changes)?;
 
@@ -1095,8 +1102,9 @@ impl IdmServerProxyWriteTransaction<'_> {
         sync_uuid: Uuid,
         sync_allow_class_set: &BTreeMap<String, SchemaClass>,
         sync_allow_attr_set: &BTreeSet<Attribute>,
+        sync_yield_attr_set: &BTreeSet<Attribute>,
         phantom_attr_set: &BTreeSet<Attribute>,
-    ) -> Result<ModifyList<ModifyInvalid>, OperationError> {
+    ) -> Result<(ModifyList<ModifyInvalid>, Vec<Attribute>), OperationError> {
         // What classes did they request for this entry to sync?
         let requested_classes = scim_ent.schemas.iter()
             .map(|schema| {
@@ -1171,8 +1179,12 @@ impl IdmServerProxyWriteTransaction<'_> {
             // Finally, establish if the attribute is syncable. Technically this could probe some attrs
             // multiple times due to how the loop is established, but in reality there are few attr overlaps.
             .filter(|a| sync_allow_attr_set.contains(*a))
-            // Add in the set of phantom syncable attrs.
-            .chain(phantom_attr_set.iter())
+            // Add in the set of phantom syncable attrs, unless their authority is yielded.
+            .chain(
+                phantom_attr_set
+                    .iter()
+                    .filter(|a| !sync_yield_attr_set.contains(*a)),
+            )
             .cloned()
             .collect();
 
@@ -1185,10 +1197,46 @@ impl IdmServerProxyWriteTransaction<'_> {
             }
         }
 
+        let mut conflicts = Vec::new();
+        let mut current_entry = None;
+
         // For each attr in the scim entry, see if it's in the sync_owned set. If so, proceed.
         for (scim_attr_name, scim_attr) in scim_ent.attrs.iter() {
             let scim_attr_name = Attribute::from(scim_attr_name.as_str());
 
+            if sync_yield_attr_set.contains(&scim_attr_name) {
+                // Kanidm has authority over this attribute, so the value from the sync source
+                // is not applied. Phantom attributes such as credentials can't be compared, but
+                // for everything else we report when the two sides disagree.
+                if phantom_attr_set.contains(&scim_attr_name) {
+                    debug!(
+                        "Ignoring yielded attribute {} for entry {}",
+                        scim_attr_name, scim_ent.id
+                    );
+                    continue;
+                }
+
+                let entry = match &current_entry {
+                    Some(entry) => entry.clone(),
+                    None => {
+                        let entry = self.qs_write.internal_search_uuid(scim_ent.id)?;
+                        current_entry = Some(entry.clone());
+                        entry
+                    }
+                };
+
+                let values = self.scim_attr_to_values(&scim_attr_name, scim_attr)?;
+
+                if scim_attr_conflicts(&entry, &scim_attr_name, values)? {
+                    warn!(
+                        "Conflict on yielded attribute {} for entry {} - the value from the sync source has been ignored",
+                        scim_attr_name, scim_ent.id
+                    );
+                    conflicts.push(scim_attr_name);
+                }
+                continue;
+            }
+
             if !sync_owned_attrs.contains(&scim_attr_name) {
                 error!(
                     "Rejecting attribute {} for entry {} which is not sync owned",
@@ -1216,7 +1264,7 @@ impl IdmServerProxyWriteTransaction<'_> {
 
         trace!(?mods);
 
-        Ok(ModifyList::new_list(mods))
+        Ok((ModifyList::new_list(mods), conflicts))
     }
 
     #[instrument(level = "info", skip_all)]
@@ -1270,6 +1318,18 @@ impl IdmServerProxyWriteTransaction<'_> {
             })
             .collect();
 
+        // The attributes that the sync connector could change, but that have been yielded.
+        let sync_yield_attr_set: BTreeSet<Attribute> = attr_snapshot
+            .values()
+            .filter_map(|attr| {
+                if attr.sync_allowed && sync_authority_set.contains(&attr.name) {
+                    Some(attr.name.clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+
         let phantom_attr_set: BTreeSet<Attribute> = attr_snapshot
             .values()
             .filter_map(|attr| {
@@ -1281,6 +1341,8 @@ impl IdmServerProxyWriteTransaction<'_> {
             })
             .collect();
 
+        let mut conflicts = BTreeSet::new();
+
         let asserts = change_entries
             .iter()
             .map(|(u, scim_ent)| {
@@ -1289,9 +1351,17 @@ impl IdmServerProxyWriteTransaction<'_> {
                     sync_uuid,
                     &sync_allow_class_set,
                     &sync_allow_attr_set,
+                    &sync_yield_attr_set,
                     &phantom_attr_set,
                 )
-                .map(|e| (*u, e))
+                .map(|(modlist, entry_conflicts)| {
+                    conflicts.extend(
+                        entry_conflicts
+                            .into_iter()
+                            .map(|attr| format!("{} {}", u, attr)),
+                    );
+                    (*u, modlist)
+                })
             })
             .collect::<Result<Vec<_>, _>>()?;
 
@@ -1302,6 +1372,62 @@ impl IdmServerProxyWriteTransaction<'_> {
             .internal_batch_modify(asserts.into_iter())
             .inspect_err(|err| {
                 error!(?err, "Unable to apply modifications to sync entries.");
+            })?;
+
+        self.scim_sync_apply_phase_3_conflicts(change_entries, sync_uuid, conflicts)
+    }
+
+    /// Record the conflicts on the sync account, so that an administrator can review them. Every
+    /// entry in the change set was re-evaluated, so their previous conflicts are replaced.
+    fn scim_sync_apply_phase_3_conflicts(
+        &mut self,
+        change_entries: &BTreeMap<Uuid, &ScimEntry>,
+        sync_uuid: Uuid,
+        mut conflicts: BTreeSet<String>,
+    ) -> Result<(), OperationError> {
+        let sync_entry = self.qs_write.internal_search_uuid(sync_uuid)?;
+
+        let previous_conflicts: BTreeSet<String> = sync_entry
+            .get_ava_set(Attribute::SyncConflict)
+            .and_then(|vs| vs.as_utf8_iter())
+            .map(|iter| iter.map(str::to_string).collect())
+            .unwrap_or_default();
+
+        conflicts.extend(
+            previous_conflicts
+                .iter()
+                .filter(|conflict| {
+                    conflict
+                        .split_once(' ')
+                        .and_then(|(uuid, _)| Uuid::parse_str(uuid).ok())
+                        .map(|uuid| !change_entries.contains_key(&uuid))
+                        .unwrap_or_default()
+                })
+                .cloned(),
+        );
+
+        if conflicts == previous_conflicts {
+            return Ok(());
+        }
+
+        if !conflicts.is_empty() {
+            warn!(
+                "Sync account has {} conflicts on yielded attributes",
+                conflicts.len()
+            );
+        }
+
+        let mut mods = vec![Modify::Purged(Attribute::SyncConflict)];
+        mods.extend(
+            conflicts
+                .into_iter()
+                .map(|conflict| Modify::Present(Attribute::SyncConflict, Value::Utf8(conflict))),
+        );
+
+        self.qs_write
+            .internal_modify_uuid(sync_uuid, &ModifyList::new_list(mods))
+            .inspect_err(|err| {
+                error!(?err, "Failed to update sync entry conflicts");
             })
     }
 
@@ -1436,6 +1562,22 @@ impl IdmServerProxyWriteTransaction<'_> {
     }
 }
 
+/// Determine if the values from the sync source disagree with the current values of the entry.
+fn scim_attr_conflicts(
+    entry: &EntrySealedCommitted,
+    attr: &Attribute,
+    values: Vec<Value>,
+) -> Result<bool, OperationError> {
+    match (entry.get_ava_set(attr), values.is_empty()) {
+        (None, true) => Ok(false),
+        (None, false) | (Some(_), true) => Ok(true),
+        (Some(current), false) => {
+            let incoming = crate::valueset::from_value_iter(values.into_iter())?;
+            Ok(!current.equal(&incoming))
+        }
+    }
+}
+
 impl IdmServerProxyReadTransaction<'_> {
     pub fn scim_sync_get_state(
         &mut self,
@@ -2917,6 +3059,102 @@ mod tests {
         assert!(idms_prox_write.commit().is_ok());
     }
 
+    #[idm_test]
+    async fn test_idm_scim_sync_yield_authority_conflict(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let (sync_uuid, ident) = test_scim_sync_apply_setup_ident(&mut idms_prox_write, ct);
+        let sse = ScimSyncUpdateEvent { ident };
+
+        let changes =
+            serde_json::from_str(TEST_SYNC_SCIM_IPA_1).expect("failed to parse scim sync");
+
+        assert!(idms_prox_write.scim_sync_apply(&sse, &changes, ct).is_ok());
+
+        // Yield the loginshell, which the sync source continues to provide.
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                sync_uuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::SyncYieldAuthority,
+                    Value::new_iutf8(Attribute::LoginShell.as_ref())
+                )
+            )
+            .is_ok());
+
+        let testuser_filter = filter!(f_eq(Attribute::Name, PartialValue::new_iname("testuser")));
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify(
+                &testuser_filter,
+                &ModifyList::new_purge_and_set(Attribute::LoginShell, Value::new_iutf8("/bin/zsh"))
+            )
+            .is_ok());
+
+        let changes =
+            serde_json::from_str(TEST_SYNC_SCIM_IPA_REFRESH_1).expect("failed to parse scim sync");
+
+        // The sync is not rejected, but the local value is retained and the conflict reported.
+        assert!(idms_prox_write.scim_sync_apply(&sse, &changes, ct).is_ok());
+
+        let testuser = idms_prox_write
+            .qs_write
+            .internal_search(testuser_filter)
+            .map(|mut results| results.pop().expect("Empty result set"))
+            .expect("Failed to access testuser");
+
+        assert_eq!(
+            testuser.get_ava_single_iutf8(Attribute::LoginShell),
+            Some("/bin/zsh")
+        );
+
+        let sync_entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(sync_uuid)
+            .expect("Failed to access sync entry");
+
+        let conflicts: Vec<_> = sync_entry
+            .get_ava_set(Attribute::SyncConflict)
+            .and_then(|vs| vs.as_utf8_iter())
+            .map(|iter| iter.collect())
+            .unwrap_or_default();
+
+        assert_eq!(
+            conflicts,
+            vec![format!("{} {}", testuser.get_uuid(), Attribute::LoginShell)]
+        );
+
+        // Once the values agree again, the conflict is resolved.
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                testuser.get_uuid(),
+                &ModifyList::new_purge_and_set(Attribute::LoginShell, Value::new_iutf8("/bin/sh"))
+            )
+            .is_ok());
+
+        let changes = ScimSyncRequest {
+            from_state: changes.to_state.clone(),
+            ..changes
+        };
+
+        assert!(idms_prox_write.scim_sync_apply(&sse, &changes, ct).is_ok());
+
+        let sync_entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(sync_uuid)
+            .expect("Failed to access sync entry");
+
+        assert!(sync_entry.get_ava_set(Attribute::SyncConflict).is_none());
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
     #[idm_test]
     async fn test_idm_scim_sync_finalise_1(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
@@ -0,0 +1,1137 @@
+//! Inbound SCIM 2.0 provisioning. This allows external systems such as HR platforms or
+//! other identity providers to create, update and remove persons and groups in Kanidm.
+//!
+//! Unlike [sync](crate::idm::scim) this does *not* grant authority over the entries to the
+//! external system - requests are performed as the authenticated identity, and are subject
+//! to the same access controls as any other client.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use kanidm_proto::scim_v1::provision::{
+    AttrPath, ScimComplexFilter, ScimFilter, ScimPatchOp, ScimPatchOperation, ScimPatchRequest,
+    ScimProvisionEmail, ScimProvisionGroup, ScimProvisionListQuery, ScimProvisionListResponse,
+    ScimProvisionMember, ScimProvisionName, ScimProvisionUser,
+};
+use kanidm_proto::scim_v1::JsonValue;
+use time::OffsetDateTime;
+
+use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction};
+use crate::prelude::*;
+
+/// The maximum number of resources that are returned in a single list response.
+const SCIM_PROVISION_PAGE_MAX: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+enum ScimResourceType {
+    User,
+    Group,
+}
+
+impl ScimResourceType {
+    fn class_filter(self) -> FC {
+        match self {
+            ScimResourceType::User => f_eq(Attribute::Class, EntryClass::Person.into()),
+            ScimResourceType::Group => f_eq(Attribute::Class, EntryClass::Group.into()),
+        }
+    }
+
+    /// Map a SCIM attribute path to the Kanidm attribute it is stored in. SCIM attribute
+    /// names are case insensitive.
+    fn attribute(self, path: &str) -> Result<Attribute, OperationError> {
+        match (self, path.to_lowercase().as_str()) {
+            (_, "id") => Ok(Attribute::Uuid),
+            (ScimResourceType::User, "username") => Ok(Attribute::Name),
+            (ScimResourceType::User, "displayname") => Ok(Attribute::DisplayName),
+            (ScimResourceType::User, "name.formatted") => Ok(Attribute::LegalName),
+            (ScimResourceType::User, "emails") | (ScimResourceType::User, "emails.value") => {
+                Ok(Attribute::Mail)
+            }
+            (ScimResourceType::User, "groups") | (ScimResourceType::User, "groups.value") => {
+                Ok(Attribute::MemberOf)
+            }
+            (ScimResourceType::Group, "displayname") => Ok(Attribute::Name),
+            (ScimResourceType::Group, "members") | (ScimResourceType::Group, "members.value") => {
+                Ok(Attribute::Member)
+            }
+            _ => Err(OperationError::InvalidAttributeName(path.to_string())),
+        }
+    }
+}
+
+fn scim_filter_to_fc<'a, T>(
+    qs: &mut T,
+    rtype: ScimResourceType,
+    filter: &ScimFilter,
+) -> Result<FC, OperationError>
+where
+    T: QueryServerTransaction<'a>,
+{
+    match filter {
+        ScimFilter::Or(l, r) => Ok(f_or(vec![
+            scim_filter_to_fc(qs, rtype, l)?,
+            scim_filter_to_fc(qs, rtype, r)?,
+        ])),
+        ScimFilter::And(l, r) => Ok(f_and(vec![
+            scim_filter_to_fc(qs, rtype, l)?,
+            scim_filter_to_fc(qs, rtype, r)?,
+        ])),
+        ScimFilter::Not(f) => Ok(f_andnot(scim_filter_to_fc(qs, rtype, f)?)),
+        ScimFilter::Present(attr_path) => rtype.attribute(&attr_path.to_string()).map(f_pres),
+        ScimFilter::Equal(attr_path, value) => {
+            scim_filter_value(qs, rtype, attr_path, value).map(|(attr, pv)| f_eq(attr, pv))
+        }
+        ScimFilter::Contains(attr_path, value) => {
+            scim_filter_value(qs, rtype, attr_path, value).map(|(attr, pv)| f_sub(attr, pv))
+        }
+        _ => {
+            warn!(filter = %filter.to_string(), "Unsupported SCIM filter operation");
+            Err(OperationError::FilterParseError)
+        }
+    }
+}
+
+fn scim_filter_value<'a, T>(
+    qs: &mut T,
+    rtype: ScimResourceType,
+    attr_path: &AttrPath,
+    value: &JsonValue,
+) -> Result<(Attribute, PartialValue), OperationError>
+where
+    T: QueryServerTransaction<'a>,
+{
+    let attr_path = attr_path.to_string();
+    let attr = rtype.attribute(&attr_path)?;
+    let value = json_as_str(&attr_path, value)?;
+    qs.clone_partialvalue(&attr, value).map(|pv| (attr, pv))
+}
+
+fn scim_provision_search<'a, T>(
+    qs: &mut T,
+    ident: &Identity,
+    rtype: ScimResourceType,
+    fc: FC,
+) -> Result<Vec<EntryReducedCommitted>, OperationError>
+where
+    T: QueryServerTransaction<'a>,
+{
+    let fc = f_and(vec![rtype.class_filter(), fc]);
+    let filter = filter!(fc.clone());
+    let filter_intent = filter_all!(fc);
+    qs.impersonate_search_ext(filter, filter_intent, ident)
+}
+
+fn scim_provision_search_single<'a, T>(
+    qs: &mut T,
+    ident: &Identity,
+    rtype: ScimResourceType,
+    fc: FC,
+) -> Result<EntryReducedCommitted, OperationError>
+where
+    T: QueryServerTransaction<'a>,
+{
+    let mut entries = scim_provision_search(qs, ident, rtype, fc)?;
+    match entries.pop() {
+        Some(entry) if entries.is_empty() => Ok(entry),
+        Some(_) => {
+            error!("Multiple entries matched a unique SCIM resource");
+            Err(OperationError::InvalidState)
+        }
+        None => Err(OperationError::NoMatchingEntries),
+    }
+}
+
+fn scim_provision_list<'a, T, R, F>(
+    qs: &mut T,
+    ident: &Identity,
+    rtype: ScimResourceType,
+    query: &ScimProvisionListQuery,
+    to_resource: F,
+) -> Result<ScimProvisionListResponse<R>, OperationError>
+where
+    T: QueryServerTransaction<'a>,
+    F: Fn(&EntryReducedCommitted) -> Result<R, OperationError>,
+{
+    let fc = match &query.filter {
+        Some(filter) => {
+            let filter = ScimFilter::from_str(filter).map_err(|err| {
+                warn!(?err, "Invalid SCIM filter");
+                OperationError::FilterParseError
+            })?;
+            scim_filter_to_fc(qs, rtype, &filter)?
+        }
+        None => f_pres(Attribute::Uuid),
+    };
+
+    let mut entries = scim_provision_search(qs, ident, rtype, fc)?;
+    // Order by uuid so that pagination is stable between requests.
+    entries.sort_unstable_by_key(|entry| entry.get_uuid());
+
+    let total_results = entries.len();
+    // startIndex is 1-based, values less than 1 are interpreted as 1.
+    let start_index = query.start_index.unwrap_or(1).max(1);
+    let count = query
+        .count
+        .unwrap_or(SCIM_PROVISION_PAGE_MAX)
+        .min(SCIM_PROVISION_PAGE_MAX);
+
+    let resources = entries
+        .iter()
+        .skip(start_index - 1)
+        .take(count)
+        .map(to_resource)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ScimProvisionListResponse::new(
+        resources,
+        total_results,
+        start_index,
+    ))
+}
+
+fn entry_to_scim_user(
+    entry: &EntryReducedCommitted,
+    ct: Duration,
+) -> Result<ScimProvisionUser, OperationError> {
+    let user_name = entry
+        .get_ava_single_iname(Attribute::Name)
+        .map(str::to_string)
+        .ok_or(OperationError::MissingAttribute(Attribute::Name))?;
+
+    let mut user = ScimProvisionUser::new(user_name);
+    user.id = Some(entry.get_uuid());
+    user.display_name = entry
+        .get_ava_single_utf8(Attribute::DisplayName)
+        .map(str::to_string);
+    user.name = entry
+        .get_ava_single_utf8(Attribute::LegalName)
+        .map(|legalname| ScimProvisionName {
+            formatted: Some(legalname.to_string()),
+            ..Default::default()
+        });
+
+    let now = OffsetDateTime::UNIX_EPOCH + ct;
+    user.active = Some(
+        entry
+            .get_ava_single_datetime(Attribute::AccountExpire)
+            .map(|expire| now < expire)
+            .unwrap_or(true),
+    );
+
+    let primary = entry.get_ava_mail_primary(Attribute::Mail);
+    user.emails = entry
+        .get_ava_iter_mail(Attribute::Mail)
+        .map(|iter| {
+            iter.map(|mail| ScimProvisionEmail {
+                value: mail.to_string(),
+                primary: Some(mail) == primary,
+                type_: None,
+            })
+            .collect()
+        })
+        .unwrap_or_default();
+
+    user.groups = entry
+        .get_ava_refer(Attribute::MemberOf)
+        .map(|groups| {
+            groups
+                .iter()
+                .map(|uuid| ScimProvisionMember {
+                    value: *uuid,
+                    display: None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(user)
+}
+
+fn entry_to_scim_group(
+    entry: &EntryReducedCommitted,
+) -> Result<ScimProvisionGroup, OperationError> {
+    let display_name = entry
+        .get_ava_single_iname(Attribute::Name)
+        .map(str::to_string)
+        .ok_or(OperationError::MissingAttribute(Attribute::Name))?;
+
+    let mut group = ScimProvisionGroup::new(display_name);
+    group.id = Some(entry.get_uuid());
+    group.members = entry
+        .get_ava_refer(Attribute::Member)
+        .map(|members| {
+            members
+                .iter()
+                .map(|uuid| ScimProvisionMember {
+                    value: *uuid,
+                    display: None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(group)
+}
+
+fn json_as_str<'a>(path: &str, value: &'a JsonValue) -> Result<&'a str, OperationError> {
+    value
+        .as_str()
+        .ok_or_else(|| OperationError::InvalidAttribute(format!("{} must be a string", path)))
+}
+
+fn json_as_bool(path: &str, value: &JsonValue) -> Result<bool, OperationError> {
+    // Some providers send booleans as strings, such as "False".
+    match value {
+        JsonValue::Bool(b) => Ok(*b),
+        JsonValue::String(s) if s.eq_ignore_ascii_case("true") => Ok(true),
+        JsonValue::String(s) if s.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(OperationError::InvalidAttribute(format!(
+            "{} must be a boolean",
+            path
+        ))),
+    }
+}
+
+/// Deserialise a multivalued attribute. A single value is accepted in place of an array.
+fn json_as_multi<T>(path: &str, value: &JsonValue) -> Result<Vec<T>, OperationError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let value = match value {
+        JsonValue::Array(_) => value.clone(),
+        _ => JsonValue::Array(vec![value.clone()]),
+    };
+    serde_json::from_value(value).map_err(|err| {
+        warn!(?err, %path, "Invalid SCIM attribute value");
+        OperationError::InvalidAttribute(format!("{} is not valid", path))
+    })
+}
+
+fn patch_value<'a>(
+    path: &str,
+    value: Option<&'a JsonValue>,
+) -> Result<&'a JsonValue, OperationError> {
+    value.ok_or_else(|| OperationError::InvalidAttribute(format!("{} requires a value", path)))
+}
+
+fn scim_email_to_value(email: &ScimProvisionEmail) -> Result<Value, OperationError> {
+    if email.primary {
+        Value::new_email_address_primary_s(&email.value)
+    } else {
+        Value::new_email_address_s(&email.value)
+    }
+    .ok_or_else(|| OperationError::InvalidAttribute(format!("{} is not valid", email.value)))
+}
+
+fn scim_user_to_modlist(
+    user: &ScimProvisionUser,
+    ct: Duration,
+) -> Result<Vec<Modify>, OperationError> {
+    let mut mods = vec![
+        Modify::Purged(Attribute::Name),
+        Modify::Present(Attribute::Name, Value::new_iname(&user.user_name)),
+        Modify::Purged(Attribute::DisplayName),
+        Modify::Present(
+            Attribute::DisplayName,
+            Value::new_utf8(scim_user_display_name(user)),
+        ),
+        Modify::Purged(Attribute::LegalName),
+        Modify::Purged(Attribute::Mail),
+        Modify::Purged(Attribute::AccountExpire),
+    ];
+
+    if let Some(formatted) = user.name.as_ref().and_then(|n| n.formatted.as_ref()) {
+        mods.push(Modify::Present(
+            Attribute::LegalName,
+            Value::new_utf8s(formatted),
+        ));
+    }
+
+    for email in user.emails.iter() {
+        mods.push(Modify::Present(
+            Attribute::Mail,
+            scim_email_to_value(email)?,
+        ));
+    }
+
+    if user.active == Some(false) {
+        mods.push(Modify::Present(
+            Attribute::AccountExpire,
+            Value::new_datetime_epoch(ct),
+        ));
+    }
+
+    Ok(mods)
+}
+
+/// Kanidm requires a display name for persons, so if one isn't provided we derive one
+/// from the name of the user.
+fn scim_user_display_name(user: &ScimProvisionUser) -> String {
+    if let Some(display_name) = &user.display_name {
+        return display_name.clone();
+    }
+
+    match &user.name {
+        Some(ScimProvisionName {
+            formatted: Some(formatted),
+            ..
+        }) => formatted.clone(),
+        Some(ScimProvisionName {
+            given_name: Some(given_name),
+            family_name: Some(family_name),
+            ..
+        }) => format!("{} {}", given_name, family_name),
+        _ => user.user_name.clone(),
+    }
+}
+
+fn scim_user_patch_to_modlist(
+    op: ScimPatchOp,
+    path: &str,
+    value: Option<&JsonValue>,
+    current: &EntryReducedCommitted,
+    ct: Duration,
+    mods: &mut Vec<Modify>,
+) -> Result<(), OperationError> {
+    let lower_path = path.to_lowercase();
+
+    // Value filters such as emails[type eq "work"].value. Kanidm doesn't store the type of
+    // an email address, so any filter other than one on the value refers to the primary address.
+    if lower_path.starts_with("emails[") {
+        let filter_path = match path.len().checked_sub(".value".len()) {
+            Some(i)
+                if path
+                    .get(i..)
+                    .is_some_and(|suffix| suffix.eq_ignore_ascii_case(".value")) =>
+            {
+                &path[..i]
+            }
+            _ => path,
+        };
+
+        let target = match ScimFilter::from_str(filter_path) {
+            Ok(ScimFilter::Complex(_, filter)) => match *filter {
+                ScimComplexFilter::Equal(attr, JsonValue::String(mail))
+                    if attr.eq_ignore_ascii_case("value") =>
+                {
+                    Some(mail)
+                }
+                _ => current
+                    .get_ava_mail_primary(Attribute::Mail)
+                    .map(str::to_string),
+            },
+            Ok(_) | Err(_) => {
+                return Err(OperationError::InvalidAttributeName(path.to_string()));
+            }
+        };
+
+        let target_is_primary = match &target {
+            Some(mail) => current.get_ava_mail_primary(Attribute::Mail) == Some(mail.as_str()),
+            None => true,
+        };
+
+        if let Some(mail) = &target {
+            mods.push(Modify::Removed(
+                Attribute::Mail,
+                PartialValue::new_email_address_s(mail),
+            ));
+        }
+
+        if op != ScimPatchOp::Remove {
+            let value = patch_value(path, value)?;
+            let value = match value {
+                JsonValue::Object(_) => serde_json::from_value::<ScimProvisionEmail>(value.clone())
+                    .map(|email| email.value)
+                    .map_err(|_| {
+                        OperationError::InvalidAttribute(format!("{} is not valid", path))
+                    })?,
+                _ => json_as_str(path, value)?.to_string(),
+            };
+            mods.push(Modify::Present(
+                Attribute::Mail,
+                scim_email_to_value(&ScimProvisionEmail {
+                    value,
+                    primary: target_is_primary,
+                    type_: None,
+                })?,
+            ));
+        }
+
+        return Ok(());
+    }
+
+    match (op, lower_path.as_str()) {
+        // Read only, or not stored by Kanidm.
+        (_, "id") | (_, "schemas") | (_, "meta") | (_, "externalid") => {
+            debug!(%path, "Ignoring SCIM attribute");
+        }
+        (_, "name.givenname") | (_, "name.familyname") => {
+            debug!(%path, "Ignoring SCIM attribute, only name.formatted is stored");
+        }
+        (_, "groups") => {
+            return Err(OperationError::InvalidAttribute(
+                "groups is read only, membership must be changed on the group".to_string(),
+            ));
+        }
+        (ScimPatchOp::Remove, "username") => {
+            return Err(OperationError::InvalidAttribute(
+                "userName may not be removed".to_string(),
+            ));
+        }
+        (_, "username") => {
+            let value = json_as_str(path, patch_value(path, value)?)?;
+            mods.push(Modify::Purged(Attribute::Name));
+            mods.push(Modify::Present(Attribute::Name, Value::new_iname(value)));
+        }
+        (ScimPatchOp::Remove, "displayname") => {
+            mods.push(Modify::Purged(Attribute::DisplayName));
+        }
+        (_, "displayname") => {
+            let value = json_as_str(path, patch_value(path, value)?)?;
+            mods.push(Modify::Purged(Attribute::DisplayName));
+            mods.push(Modify::Present(
+                Attribute::DisplayName,
+                Value::new_utf8s(value),
+            ));
+        }
+        (ScimPatchOp::Remove, "name") | (ScimPatchOp::Remove, "name.formatted") => {
+            mods.push(Modify::Purged(Attribute::LegalName));
+        }
+        (_, "name") => {
+            let name: ScimProvisionName = serde_json::from_value(patch_value(path, value)?.clone())
+                .map_err(|_| OperationError::InvalidAttribute(format!("{} is not valid", path)))?;
+            if let Some(formatted) = name.formatted {
+                mods.push(Modify::Purged(Attribute::LegalName));
+                mods.push(Modify::Present(
+                    Attribute::LegalName,
+                    Value::new_utf8(formatted),
+                ));
+            }
+        }
+        (_, "name.formatted") => {
+            let value = json_as_str(path, patch_value(path, value)?)?;
+            mods.push(Modify::Purged(Attribute::LegalName));
+            mods.push(Modify::Present(
+                Attribute::LegalName,
+                Value::new_utf8s(value),
+            ));
+        }
+        (ScimPatchOp::Remove, "active") => {
+            mods.push(Modify::Purged(Attribute::AccountExpire));
+        }
+        (_, "active") => {
+            let active = json_as_bool(path, patch_value(path, value)?)?;
+            mods.push(Modify::Purged(Attribute::AccountExpire));
+            if !active {
+                mods.push(Modify::Present(
+                    Attribute::AccountExpire,
+                    Value::new_datetime_epoch(ct),
+                ));
+            }
+        }
+        (ScimPatchOp::Remove, "emails") => match value {
+            Some(value) => {
+                for email in json_as_multi::<ScimProvisionEmail>(path, value)? {
+                    mods.push(Modify::Removed(
+                        Attribute::Mail,
+                        PartialValue::new_email_address_s(&email.value),
+                    ));
+                }
+            }
+            None => mods.push(Modify::Purged(Attribute::Mail)),
+        },
+        (_, "emails") => {
+            if op == ScimPatchOp::Replace {
+                mods.push(Modify::Purged(Attribute::Mail));
+            }
+            for email in json_as_multi::<ScimProvisionEmail>(path, patch_value(path, value)?)? {
+                mods.push(Modify::Present(
+                    Attribute::Mail,
+                    scim_email_to_value(&email)?,
+                ));
+            }
+        }
+        _ => return Err(OperationError::InvalidAttributeName(path.to_string())),
+    }
+
+    Ok(())
+}
+
+fn scim_group_patch_to_modlist(
+    op: ScimPatchOp,
+    path: &str,
+    value: Option<&JsonValue>,
+    mods: &mut Vec<Modify>,
+) -> Result<(), OperationError> {
+    let lower_path = path.to_lowercase();
+
+    // Removal of a specific member, such as members[value eq "..."]
+    if lower_path.starts_with("members[") {
+        let member = match (op, ScimFilter::from_str(path)) {
+            (ScimPatchOp::Remove, Ok(ScimFilter::Complex(_, filter))) => match *filter {
+                ScimComplexFilter::Equal(attr, JsonValue::String(member))
+                    if attr.eq_ignore_ascii_case("value") =>
+                {
+                    Uuid::parse_str(&member).map_err(|_| OperationError::InvalidUuid)?
+                }
+                _ => return Err(OperationError::InvalidAttributeName(path.to_string())),
+            },
+            _ => return Err(OperationError::InvalidAttributeName(path.to_string())),
+        };
+        mods.push(Modify::Removed(
+            Attribute::Member,
+            PartialValue::Refer(member),
+        ));
+        return Ok(());
+    }
+
+    match (op, lower_path.as_str()) {
+        (_, "id") | (_, "schemas") | (_, "meta") | (_, "externalid") => {
+            debug!(%path, "Ignoring SCIM attribute");
+        }
+        (ScimPatchOp::Remove, "displayname") => {
+            return Err(OperationError::InvalidAttribute(
+                "displayName may not be removed".to_string(),
+            ));
+        }
+        (_, "displayname") => {
+            let value = json_as_str(path, patch_value(path, value)?)?;
+            mods.push(Modify::Purged(Attribute::Name));
+            mods.push(Modify::Present(Attribute::Name, Value::new_iname(value)));
+        }
+        (ScimPatchOp::Remove, "members") => match value {
+            Some(value) => {
+                for member in json_as_multi::<ScimProvisionMember>(path, value)? {
+                    mods.push(Modify::Removed(
+                        Attribute::Member,
+                        PartialValue::Refer(member.value),
+                    ));
+                }
+            }
+            None => mods.push(Modify::Purged(Attribute::Member)),
+        },
+        (_, "members") => {
+            if op == ScimPatchOp::Replace {
+                mods.push(Modify::Purged(Attribute::Member));
+            }
+            for member in json_as_multi::<ScimProvisionMember>(path, patch_value(path, value)?)? {
+                mods.push(Modify::Present(
+                    Attribute::Member,
+                    Value::Refer(member.value),
+                ));
+            }
+        }
+        _ => return Err(OperationError::InvalidAttributeName(path.to_string())),
+    }
+
+    Ok(())
+}
+
+/// Apply each operation of a patch request. Operations without a path carry an object
+/// whose keys are the attributes to add or replace.
+fn scim_patch_to_modlist<F>(
+    patch: &ScimPatchRequest,
+    mut apply: F,
+) -> Result<ModifyList<ModifyInvalid>, OperationError>
+where
+    F: FnMut(ScimPatchOp, &str, Option<&JsonValue>, &mut Vec<Modify>) -> Result<(), OperationError>,
+{
+    let mut mods = Vec::with_capacity(patch.operations.len());
+
+    for ScimPatchOperation { op, path, value } in patch.operations.iter() {
+        match (path, value) {
+            (Some(path), value) => apply(*op, path.as_str(), value.as_ref(), &mut mods)?,
+            (None, Some(JsonValue::Object(attrs))) if *op != ScimPatchOp::Remove => {
+                for (path, value) in attrs.iter() {
+                    apply(*op, path, Some(value), &mut mods)?;
+                }
+            }
+            (None, _) => {
+                return Err(OperationError::InvalidAttribute(
+                    "patch operation requires a path".to_string(),
+                ));
+            }
+        }
+    }
+
+    if mods.is_empty() {
+        return Err(OperationError::EmptyRequest);
+    }
+
+    Ok(ModifyList::new_list(mods))
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    pub fn scim_provision_user_get(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+        ct: Duration,
+    ) -> Result<ScimProvisionUser, OperationError> {
+        scim_provision_search_single(
+            &mut self.qs_read,
+            ident,
+            ScimResourceType::User,
+            f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)),
+        )
+        .and_then(|entry| entry_to_scim_user(&entry, ct))
+    }
+
+    pub fn scim_provision_user_list(
+        &mut self,
+        ident: &Identity,
+        query: &ScimProvisionListQuery,
+        ct: Duration,
+    ) -> Result<ScimProvisionListResponse<ScimProvisionUser>, OperationError> {
+        scim_provision_list(
+            &mut self.qs_read,
+            ident,
+            ScimResourceType::User,
+            query,
+            |entry| entry_to_scim_user(entry, ct),
+        )
+    }
+
+    pub fn scim_provision_group_get(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+    ) -> Result<ScimProvisionGroup, OperationError> {
+        scim_provision_search_single(
+            &mut self.qs_read,
+            ident,
+            ScimResourceType::Group,
+            f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)),
+        )
+        .and_then(|entry| entry_to_scim_group(&entry))
+    }
+
+    pub fn scim_provision_group_list(
+        &mut self,
+        ident: &Identity,
+        query: &ScimProvisionListQuery,
+    ) -> Result<ScimProvisionListResponse<ScimProvisionGroup>, OperationError> {
+        scim_provision_list(
+            &mut self.qs_read,
+            ident,
+            ScimResourceType::Group,
+            query,
+            entry_to_scim_group,
+        )
+    }
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    fn scim_provision_modify(
+        &mut self,
+        ident: &Identity,
+        rtype: ScimResourceType,
+        uuid: Uuid,
+        modlist: &ModifyList<ModifyInvalid>,
+    ) -> Result<(), OperationError> {
+        let fc = f_and(vec![
+            rtype.class_filter(),
+            f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)),
+        ]);
+        let filter = filter!(fc.clone());
+        let filter_intent = filter_all!(fc);
+
+        self.qs_write
+            .impersonate_modify(&filter, &filter_intent, modlist, ident)
+    }
+
+    fn scim_provision_delete(
+        &mut self,
+        ident: &Identity,
+        rtype: ScimResourceType,
+        uuid: Uuid,
+    ) -> Result<(), OperationError> {
+        let filter = filter!(f_and(vec![
+            rtype.class_filter(),
+            f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)),
+        ]));
+
+        let de = DeleteEvent::from_parts(ident.clone(), &filter, &mut self.qs_write)?;
+        self.qs_write.delete(&de)
+    }
+
+    pub fn scim_provision_user_create(
+        &mut self,
+        ident: &Identity,
+        user: &ScimProvisionUser,
+        ct: Duration,
+    ) -> Result<ScimProvisionUser, OperationError> {
+        let mut entry: EntryInitNew = Entry::new();
+        entry.add_ava(Attribute::Class, EntryClass::Object.to_value());
+        entry.add_ava(Attribute::Class, EntryClass::Account.to_value());
+        entry.add_ava(Attribute::Class, EntryClass::Person.to_value());
+        entry.add_ava(Attribute::Name, Value::new_iname(&user.user_name));
+        entry.add_ava(
+            Attribute::DisplayName,
+            Value::new_utf8(scim_user_display_name(user)),
+        );
+
+        for email in user.emails.iter() {
+            entry.add_ava(Attribute::Mail, scim_email_to_value(email)?);
+        }
+
+        if user.active == Some(false) {
+            entry.add_ava(Attribute::AccountExpire, Value::new_datetime_epoch(ct));
+        }
+
+        let ce = CreateEvent {
+            ident: ident.clone(),
+            entries: vec![entry],
+        };
+        self.qs_write.create(&ce)?;
+
+        let created = scim_provision_search_single(
+            &mut self.qs_write,
+            ident,
+            ScimResourceType::User,
+            f_eq(Attribute::Name, PartialValue::new_iname(&user.user_name)),
+        )?;
+        let uuid = created.get_uuid();
+
+        // Legal names are sensitive and can't be set at creation, so they are added
+        // with the separate pii access.
+        match user.name.as_ref().and_then(|n| n.formatted.as_ref()) {
+            Some(formatted) => {
+                let modlist =
+                    ModifyList::new_append(Attribute::LegalName, Value::new_utf8s(formatted));
+                self.scim_provision_modify(ident, ScimResourceType::User, uuid, &modlist)?;
+                self.scim_provision_user_get_rw(ident, uuid, ct)
+            }
+            None => entry_to_scim_user(&created, ct),
+        }
+    }
+
+    pub fn scim_provision_user_replace(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+        user: &ScimProvisionUser,
+        ct: Duration,
+    ) -> Result<ScimProvisionUser, OperationError> {
+        let modlist = ModifyList::new_list(scim_user_to_modlist(user, ct)?);
+        self.scim_provision_modify(ident, ScimResourceType::User, uuid, &modlist)?;
+
+        self.scim_provision_user_get_rw(ident, uuid, ct)
+    }
+
+    pub fn scim_provision_user_patch(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+        patch: &ScimPatchRequest,
+        ct: Duration,
+    ) -> Result<ScimProvisionUser, OperationError> {
+        let current = scim_provision_search_single(
+            &mut self.qs_write,
+            ident,
+            ScimResourceType::User,
+            f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)),
+        )?;
+
+        let modlist = scim_patch_to_modlist(patch, |op, path, value, mods| {
+            scim_user_patch_to_modlist(op, path, value, &current, ct, mods)
+        })?;
+        self.scim_provision_modify(ident, ScimResourceType::User, uuid, &modlist)?;
+
+        self.scim_provision_user_get_rw(ident, uuid, ct)
+    }
+
+    pub fn scim_provision_user_delete(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+    ) -> Result<(), OperationError> {
+        self.scim_provision_delete(ident, ScimResourceType::User, uuid)
+    }
+
+    pub fn scim_provision_group_create(
+        &mut self,
+        ident: &Identity,
+        group: &ScimProvisionGroup,
+    ) -> Result<ScimProvisionGroup, OperationError> {
+        let mut entry: EntryInitNew = Entry::new();
+        entry.add_ava(Attribute::Class, EntryClass::Object.to_value());
+        entry.add_ava(Attribute::Class, EntryClass::Group.to_value());
+        entry.add_ava(Attribute::Name, Value::new_iname(&group.display_name));
+
+        for member in group.members.iter() {
+            entry.add_ava(Attribute::Member, Value::Refer(member.value));
+        }
+
+        let ce = CreateEvent {
+            ident: ident.clone(),
+            entries: vec![entry],
+        };
+        self.qs_write.create(&ce)?;
+
+        scim_provision_search_single(
+            &mut self.qs_write,
+            ident,
+            ScimResourceType::Group,
+            f_eq(
+                Attribute::Name,
+                PartialValue::new_iname(&group.display_name),
+            ),
+        )
+        .and_then(|entry| entry_to_scim_group(&entry))
+    }
+
+    pub fn scim_provision_group_replace(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+        group: &ScimProvisionGroup,
+    ) -> Result<ScimProvisionGroup, OperationError> {
+        let mut mods = vec![
+            Modify::Purged(Attribute::Name),
+            Modify::Present(Attribute::Name, Value::new_iname(&group.display_name)),
+            Modify::Purged(Attribute::Member),
+        ];
+        mods.extend(
+            group
+                .members
+                .iter()
+                .map(|member| Modify::Present(Attribute::Member, Value::Refer(member.value))),
+        );
+
+        let modlist = ModifyList::new_list(mods);
+        self.scim_provision_modify(ident, ScimResourceType::Group, uuid, &modlist)?;
+
+        self.scim_provision_group_get_rw(ident, uuid)
+    }
+
+    pub fn scim_provision_group_patch(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+        patch: &ScimPatchRequest,
+    ) -> Result<ScimProvisionGroup, OperationError> {
+        let modlist = scim_patch_to_modlist(patch, scim_group_patch_to_modlist)?;
+        self.scim_provision_modify(ident, ScimResourceType::Group, uuid, &modlist)?;
+
+        self.scim_provision_group_get_rw(ident, uuid)
+    }
+
+    pub fn scim_provision_group_delete(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+    ) -> Result<(), OperationError> {
+        self.scim_provision_delete(ident, ScimResourceType::Group, uuid)
+    }
+
+    fn scim_provision_user_get_rw(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+        ct: Duration,
+    ) -> Result<ScimProvisionUser, OperationError> {
+        scim_provision_search_single(
+            &mut self.qs_write,
+            ident,
+            ScimResourceType::User,
+            f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)),
+        )
+        .and_then(|entry| entry_to_scim_user(&entry, ct))
+    }
+
+    fn scim_provision_group_get_rw(
+        &mut self,
+        ident: &Identity,
+        uuid: Uuid,
+    ) -> Result<ScimProvisionGroup, OperationError> {
+        scim_provision_search_single(
+            &mut self.qs_write,
+            ident,
+            ScimResourceType::Group,
+            f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)),
+        )
+        .and_then(|entry| entry_to_scim_group(&entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::idm::server::IdmServerProxyWriteTransaction;
+    use crate::prelude::*;
+    use kanidm_proto::scim_v1::provision::*;
+    use serde_json::json;
+
+    fn test_scim_provision_ident(
+        idms_prox_write: &mut IdmServerProxyWriteTransaction<'_>,
+    ) -> Identity {
+        let idm_admin = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("Unable to find idm_admin");
+        Identity::from_impersonate_entry_readwrite(idm_admin)
+    }
+
+    #[idm_test]
+    async fn test_idm_scim_provision_user_lifecycle(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let ident = test_scim_provision_ident(&mut idms_prox_write);
+
+        let mut user = ScimProvisionUser::new("bjensen".to_string());
+        user.name = Some(ScimProvisionName {
+            formatted: None,
+            given_name: Some("Barbara".to_string()),
+            family_name: Some("Jensen".to_string()),
+        });
+        user.emails = vec![ScimProvisionEmail {
+            value: "bjensen@example.com".to_string(),
+            primary: true,
+            type_: Some("work".to_string()),
+        }];
+
+        let created = idms_prox_write
+            .scim_provision_user_create(&ident, &user, ct)
+            .expect("Failed to create user");
+        let uuid = created.id.expect("No id returned");
+
+        assert_eq!(created.user_name, "bjensen");
+        assert_eq!(created.display_name.as_deref(), Some("Barbara Jensen"));
+        assert_eq!(created.active, Some(true));
+        assert_eq!(created.emails.len(), 1);
+        assert!(created.emails[0].primary);
+
+        // Deactivate the user, and change their primary email as Azure AD would.
+        let patch: ScimPatchRequest = serde_json::from_value(json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+            "Operations": [
+                { "op": "Replace", "path": "active", "value": "False" },
+                { "op": "Replace", "path": "emails[type eq \"work\"].value", "value": "babs@example.com" },
+                { "op": "Replace", "value": { "displayName": "Babs Jensen" } }
+            ]
+        }))
+        .expect("Invalid patch");
+
+        let patched = idms_prox_write
+            .scim_provision_user_patch(&ident, uuid, &patch, ct)
+            .expect("Failed to patch user");
+
+        assert_eq!(patched.active, Some(false));
+        assert_eq!(patched.display_name.as_deref(), Some("Babs Jensen"));
+        assert_eq!(patched.emails.len(), 1);
+        assert_eq!(patched.emails[0].value, "babs@example.com");
+        assert!(patched.emails[0].primary);
+
+        // Groups are read only on the user.
+        let patch = ScimPatchRequest::new(vec![ScimPatchOperation {
+            op: ScimPatchOp::Add,
+            path: Some("groups".to_string()),
+            value: Some(json!([{ "value": UUID_IDM_ADMINS }])),
+        }]);
+        assert!(idms_prox_write
+            .scim_provision_user_patch(&ident, uuid, &patch, ct)
+            .is_err());
+
+        idms_prox_write
+            .scim_provision_user_delete(&ident, uuid)
+            .expect("Failed to delete user");
+
+        assert!(matches!(
+            idms_prox_write.scim_provision_user_delete(&ident, uuid),
+            Err(OperationError::NoMatchingEntries)
+        ));
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    #[idm_test]
+    async fn test_idm_scim_provision_group_membership(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let ident = test_scim_provision_ident(&mut idms_prox_write);
+
+        let user_a = idms_prox_write
+            .scim_provision_user_create(&ident, &ScimProvisionUser::new("user_a".to_string()), ct)
+            .expect("Failed to create user")
+            .id
+            .expect("No id returned");
+        let user_b = idms_prox_write
+            .scim_provision_user_create(&ident, &ScimProvisionUser::new("user_b".to_string()), ct)
+            .expect("Failed to create user")
+            .id
+            .expect("No id returned");
+
+        let mut group = ScimProvisionGroup::new("engineering".to_string());
+        group.members = vec![ScimProvisionMember {
+            value: user_a,
+            display: None,
+        }];
+
+        let group = idms_prox_write
+            .scim_provision_group_create(&ident, &group)
+            .expect("Failed to create group");
+        let group_uuid = group.id.expect("No id returned");
+        assert_eq!(group.members.len(), 1);
+
+        let patch = ScimPatchRequest::new(vec![
+            ScimPatchOperation {
+                op: ScimPatchOp::Add,
+                path: Some("members".to_string()),
+                value: Some(json!([{ "value": user_b }])),
+            },
+            ScimPatchOperation {
+                op: ScimPatchOp::Remove,
+                path: Some(format!("members[value eq \"{}\"]", user_a)),
+                value: None,
+            },
+        ]);
+
+        let group = idms_prox_write
+            .scim_provision_group_patch(&ident, group_uuid, &patch)
+            .expect("Failed to patch group");
+        assert_eq!(group.members.len(), 1);
+        assert_eq!(group.members[0].value, user_b);
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        // Membership is reflected on the user, and can be found by filter.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let ident = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .map(Identity::from_impersonate_entry_readwrite)
+            .expect("Unable to find idm_admin");
+
+        let user = idms_prox_read
+            .scim_provision_user_get(&ident, user_b, ct)
+            .expect("Failed to get user");
+        assert!(user.groups.iter().any(|g| g.value == group_uuid));
+
+        let query = ScimProvisionListQuery {
+            filter: Some("userName eq \"user_a\" or userName eq \"user_b\"".to_string()),
+            start_index: Some(2),
+            count: Some(10),
+        };
+        let list = idms_prox_read
+            .scim_provision_user_list(&ident, &query, ct)
+            .expect("Failed to list users");
+        assert_eq!(list.total_results, 2);
+        assert_eq!(list.start_index, 2);
+        assert_eq!(list.resources.len(), 1);
+
+        let query = ScimProvisionListQuery {
+            filter: Some("userName gt \"user_a\"".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            idms_prox_read.scim_provision_user_list(&ident, &query, ct),
+            Err(OperationError::FilterParseError)
+        ));
+    }
+}
@@ -1,18 +1,21 @@
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use kanidm_lib_crypto::CryptoPolicy;
 
-use compact_jwt::{Jwk, JwsCompact};
+use compact_jwt::{compact::JwkKeySet, Jwk, JwsCompact};
 use concread::bptree::{BptreeMap, BptreeMapReadTxn, BptreeMapWriteTxn};
 use concread::cowcell::CowCellReadTxn;
 use concread::hashmap::HashMap;
 use kanidm_proto::internal::{
-    ApiToken, BackupCodesView, CredentialStatus, PasswordFeedback, RadiusAuthToken, ScimSyncToken,
+    ApiToken, BackupCodesView, CredentialSoftLockStatus, CredentialStatus, EffectiveAccountPolicy,
+    PasswordFeedback, RadiusAuthToken, ScimSyncToken, SelfTestCheck, SelfTestItem, SelfTestStatus,
     UatPurpose, UserAuthToken,
 };
-use kanidm_proto::v1::{UnixGroupToken, UnixUserToken};
+use kanidm_proto::v1::{AuthCredential, AuthIssueSession, AuthMech, UnixGroupToken, UnixUserToken};
 use rand::prelude::*;
 use tokio::sync::mpsc::{
     unbounded_channel as unbounded, UnboundedReceiver as Receiver, UnboundedSender as Sender,
@@ -20,31 +23,46 @@ use tokio::sync::mpsc::{
 use tokio::sync::{Mutex, Semaphore};
 use tracing::trace;
 use url::Url;
-use webauthn_rs::prelude::{Webauthn, WebauthnBuilder};
+use webauthn_rs::prelude::{
+    PublicKeyCredential, RequestChallengeResponse, Webauthn, WebauthnBuilder,
+};
 
 use super::event::ReadBackupCodeEvent;
 use super::ldap::{LdapBoundToken, LdapSession};
+use crate::credential::breach::PasswordBreachFilter;
+use crate::credential::denylist::password_denylist_match;
 use crate::credential::{softlock::CredSoftLock, Credential};
 use crate::idm::account::Account;
+use crate::idm::accountpolicy::ResolvedAccountPolicy;
+use crate::idm::accountrecovery::AccountRecoveryState;
 use crate::idm::application::{
     GenerateApplicationPasswordEvent, LdapApplications, LdapApplicationsReadTransaction,
     LdapApplicationsWriteTransaction,
 };
-use crate::idm::audit::AuditEvent;
-use crate::idm::authsession::{AuthSession, AuthSessionData};
+use crate::idm::audit::{send_activity, ActivitySender, AuditEvent, AuditRecord};
+use crate::idm::authmetrics::{AuthFunnelMetrics, AuthFunnelSnapshot, AuthFunnelStep};
+use crate::idm::authsession::{AuthSession, AuthSessionData, DiscoverableChallenge};
+use crate::idm::breakglass::BreakGlass;
 use crate::idm::credupdatesession::CredentialUpdateSessionMutex;
 use crate::idm::delayed::{
-    AuthSessionRecord, BackupCodeRemoval, DelayedAction, PasswordUpgrade, UnixPasswordUpgrade,
-    WebauthnCounterIncrement,
+    ApiTokenUse, AuthSessionRecord, BackupCodeRemoval, DelayedAction, PasswordUpgrade, SessionUse,
+    UnixPasswordUpgrade, WebauthnCounterIncrement,
+};
+use crate::idm::emaillink::EmailLinkMessage;
+use crate::idm::geoip::GeoIp;
+use crate::idm::hbac::{check_login_host, load_unix_hbac_policy, load_unix_login_hosts};
+use crate::idm::notification::{
+    send_notification, NotificationSender, SecurityNotification, SOFTLOCK_NOTIFICATION_THRESHOLD,
 };
+use crate::idm::oidcupstream::{oidc_upstreams_load, OidcUpstreamExchange};
 
 #[cfg(test)]
 use crate::idm::event::PasswordChangeEvent;
-use crate::idm::event::{AuthEvent, AuthEventStep, AuthResult};
+use crate::idm::event::{AuthEvent, AuthEventStep, AuthEventStepCred, AuthResult};
 use crate::idm::event::{
     CredentialStatusEvent, LdapAuthEvent, LdapTokenAuthEvent, RadiusAuthTokenEvent,
-    RegenerateRadiusSecretEvent, UnixGroupTokenEvent, UnixPasswordChangeEvent, UnixUserAuthEvent,
-    UnixUserTokenEvent,
+    ReadAccountPolicyEvent, RegenerateRadiusSecretEvent, UnixGroupTokenEvent,
+    UnixPasswordChangeEvent, UnixUserAuthEvent, UnixUserTokenEvent,
 };
 use crate::idm::group::{Group, Unix};
 use crate::idm::oauth2::{
@@ -54,12 +72,16 @@ use crate::idm::oauth2::{
 use crate::idm::radius::RadiusAccount;
 use crate::idm::scim::SyncAccount;
 use crate::idm::serviceaccount::ServiceAccount;
+use crate::idm::session_binding::check_session_binding;
+use crate::idm::whitepages::{WhitePagesAccess, WhitePagesLimiter, WhitePagesPolicy};
 use crate::idm::AuthState;
+use crate::metrics::{ServerMetrics, ServerMetricsSnapshot};
 use crate::prelude::*;
+use crate::server::access::Access;
 use crate::server::keys::KeyProvidersTransaction;
 use crate::server::DomainInfo;
 use crate::utils::{password_from_random, readable_password_from_random, uuid_from_duration, Sid};
-use crate::value::{Session, SessionState};
+use crate::value::{ApiTokenPermission, CredentialFactor, CredentialUsage, Session, SessionState};
 
 pub(crate) type AuthSessionMutex = Arc<Mutex<AuthSession>>;
 pub(crate) type CredSoftLockMutex = Arc<Mutex<CredSoftLock>>;
@@ -73,25 +95,45 @@ pub struct IdmServer {
     // in memory caches related to locking.
     session_ticket: Semaphore,
     sessions: BptreeMap<Uuid, AuthSessionMutex>,
+    /// Passkey challenges that were issued before the account is known.
+    discoverable_challenges: BptreeMap<Uuid, DiscoverableChallenge>,
     softlocks: HashMap<Uuid, CredSoftLockMutex>,
     /// A set of in progress credential registrations
     cred_update_sessions: BptreeMap<Uuid, CredentialUpdateSessionMutex>,
+    /// A set of in progress account recoveries
+    account_recovery: BptreeMap<Uuid, AccountRecoveryState>,
     /// Reference to the query server.
     qs: QueryServer,
     /// The configured crypto policy for the IDM server. Later this could be transactional and loaded from the db similar to access. But today it's just to allow dynamic pbkdf2rounds
     crypto_policy: CryptoPolicy,
     async_tx: Sender<DelayedAction>,
     audit_tx: Sender<AuditEvent>,
+    activity_tx: OnceLock<Sender<AuditEvent>>,
+    notify_tx: OnceLock<Sender<SecurityNotification>>,
+    email_link_tx: OnceLock<Sender<EmailLinkMessage>>,
     /// [Webauthn] verifier/config
     webauthn: Webauthn,
     oauth2rs: Arc<Oauth2ResourceServers>,
     applications: Arc<LdapApplications>,
+    auth_metrics: AuthFunnelMetrics,
+    /// The emergency authentication path for the admin account, if a break glass key is
+    /// configured.
+    break_glass: OnceLock<BreakGlass>,
+    /// The dataset of breached passwords that new passwords are checked against, if one is
+    /// configured.
+    password_breach: OnceLock<PasswordBreachFilter>,
+    /// The databases that addresses are located with, if any are configured. This is shared
+    /// with the audit receiver so that audit records can include the location of their source.
+    geoip: Arc<OnceLock<GeoIp>>,
+    /// The rate limit of anonymous white pages searches.
+    white_pages: WhitePagesLimiter,
 }
 
 /// Contains methods that require writes, but in the context of writing to the idm in memory structures (maybe the query server too). This is things like authentication.
 pub struct IdmServerAuthTransaction<'a> {
     pub(crate) session_ticket: &'a Semaphore,
     pub(crate) sessions: &'a BptreeMap<Uuid, AuthSessionMutex>,
+    pub(crate) discoverable_challenges: &'a BptreeMap<Uuid, DiscoverableChallenge>,
     pub(crate) softlocks: &'a HashMap<Uuid, CredSoftLockMutex>,
 
     pub qs_read: QueryServerReadTransaction<'a>,
@@ -100,8 +142,15 @@ pub struct IdmServerAuthTransaction<'a> {
     // For flagging eventual actions.
     pub(crate) async_tx: Sender<DelayedAction>,
     pub(crate) audit_tx: Sender<AuditEvent>,
+    pub(crate) activity_tx: ActivitySender,
+    pub(crate) notify_tx: NotificationSender,
+    pub(crate) email_link_tx: Option<Sender<EmailLinkMessage>>,
     pub(crate) webauthn: &'a Webauthn,
     pub(crate) applications: LdapApplicationsReadTransaction,
+    pub(crate) auth_metrics: &'a AuthFunnelMetrics,
+    pub(crate) break_glass: Option<&'a BreakGlass>,
+    pub(crate) white_pages: &'a WhitePagesLimiter,
+    pub(crate) geoip: Option<&'a GeoIp>,
 }
 
 pub struct IdmServerCredUpdateTransaction<'a> {
@@ -110,12 +159,17 @@ pub struct IdmServerCredUpdateTransaction<'a> {
     pub(crate) webauthn: &'a Webauthn,
     pub(crate) cred_update_sessions: BptreeMapReadTxn<'a, Uuid, CredentialUpdateSessionMutex>,
     pub(crate) crypto_policy: &'a CryptoPolicy,
+    pub(crate) password_breach: Option<&'a PasswordBreachFilter>,
 }
 
 /// This contains read-only methods, like getting users, groups and other structured content.
 pub struct IdmServerProxyReadTransaction<'a> {
     pub qs_read: QueryServerReadTransaction<'a>,
     pub(crate) oauth2rs: Oauth2ResourceServersReadTransaction,
+    // For flagging eventual actions.
+    pub(crate) async_tx: Sender<DelayedAction>,
+    pub(crate) break_glass: Option<&'a BreakGlass>,
+    pub(crate) white_pages: &'a WhitePagesLimiter,
 }
 
 pub struct IdmServerProxyWriteTransaction<'a> {
@@ -124,11 +178,21 @@ pub struct IdmServerProxyWriteTransaction<'a> {
     pub qs_write: QueryServerWriteTransaction<'a>,
     /// Associate to an event origin ID, which has a TS and a UUID instead
     pub(crate) cred_update_sessions: BptreeMapWriteTxn<'a, Uuid, CredentialUpdateSessionMutex>,
+    pub(crate) account_recovery: BptreeMapWriteTxn<'a, Uuid, AccountRecoveryState>,
     pub(crate) sid: Sid,
     crypto_policy: &'a CryptoPolicy,
     webauthn: &'a Webauthn,
+    // For flagging eventual actions.
+    pub(crate) async_tx: Sender<DelayedAction>,
+    pub(crate) audit_tx: Sender<AuditEvent>,
+    activity_tx: ActivitySender,
+    notify_tx: NotificationSender,
+    /// Notifications are only sent once the changes they describe are committed.
+    pending_notifications: Vec<SecurityNotification>,
     pub(crate) oauth2rs: Oauth2ResourceServersWriteTransaction<'a>,
     pub(crate) applications: LdapApplicationsWriteTransaction<'a>,
+    pub(crate) break_glass: Option<&'a BreakGlass>,
+    pub(crate) white_pages: &'a WhitePagesLimiter,
 }
 
 pub struct IdmServerDelayed {
@@ -137,6 +201,7 @@ pub struct IdmServerDelayed {
 
 pub struct IdmServerAudit {
     pub(crate) audit_rx: Receiver<AuditEvent>,
+    geoip: Arc<OnceLock<GeoIp>>,
 }
 
 impl IdmServer {
@@ -155,6 +220,7 @@ impl IdmServer {
 
         let (async_tx, async_rx) = unbounded();
         let (audit_tx, audit_rx) = unbounded();
+        let geoip = Arc::new(OnceLock::new());
 
         // Get the domain name, as the relying party id.
         let (rp_id, rp_name, domain_level, oauth2rs_set, application_set) = {
@@ -215,18 +281,28 @@ impl IdmServer {
             IdmServer {
                 session_ticket: Semaphore::new(1),
                 sessions: BptreeMap::new(),
+                discoverable_challenges: BptreeMap::new(),
                 softlocks: HashMap::new(),
                 cred_update_sessions: BptreeMap::new(),
+                account_recovery: BptreeMap::new(),
                 qs,
                 crypto_policy,
                 async_tx,
                 audit_tx,
+                activity_tx: OnceLock::new(),
+                notify_tx: OnceLock::new(),
+                email_link_tx: OnceLock::new(),
                 webauthn,
                 oauth2rs: Arc::new(oauth2rs),
                 applications: Arc::new(applications),
+                auth_metrics: AuthFunnelMetrics::default(),
+                break_glass: OnceLock::new(),
+                password_breach: OnceLock::new(),
+                geoip: geoip.clone(),
+                white_pages: WhitePagesLimiter::default(),
             },
             IdmServerDelayed { async_rx },
-            IdmServerAudit { audit_rx },
+            IdmServerAudit { audit_rx, geoip },
         ))
     }
 
@@ -241,13 +317,21 @@ impl IdmServer {
         Ok(IdmServerAuthTransaction {
             session_ticket: &self.session_ticket,
             sessions: &self.sessions,
+            discoverable_challenges: &self.discoverable_challenges,
             softlocks: &self.softlocks,
             qs_read,
             sid,
             async_tx: self.async_tx.clone(),
             audit_tx: self.audit_tx.clone(),
+            activity_tx: self.activity_tx.get().cloned(),
+            notify_tx: self.notify_tx.get().cloned(),
+            email_link_tx: self.email_link_tx.get().cloned(),
             webauthn: &self.webauthn,
             applications: self.applications.read(),
+            auth_metrics: &self.auth_metrics,
+            break_glass: self.break_glass.get(),
+            white_pages: &self.white_pages,
+            geoip: self.geoip.get(),
         })
     }
 
@@ -259,6 +343,16 @@ impl IdmServer {
         self.qs.d_info.read()
     }
 
+    /// Enable or disable maintenance mode. While enabled, changes requested by clients are
+    /// refused, but reads and authentication continue.
+    pub fn set_maintenance_mode(&self, enabled: bool) {
+        self.qs.set_maintenance_mode(enabled)
+    }
+
+    pub fn maintenance_mode(&self) -> bool {
+        self.qs.maintenance_mode()
+    }
+
     /// Read from the database, in a transaction.
     #[instrument(level = "debug", skip_all)]
     pub async fn proxy_read(&self) -> Result<IdmServerProxyReadTransaction<'_>, OperationError> {
@@ -266,7 +360,9 @@ impl IdmServer {
         Ok(IdmServerProxyReadTransaction {
             qs_read,
             oauth2rs: self.oauth2rs.read(),
-            // async_tx: self.async_tx.clone(),
+            async_tx: self.async_tx.clone(),
+            break_glass: self.break_glass.get(),
+            white_pages: &self.white_pages,
         })
     }
 
@@ -283,12 +379,20 @@ impl IdmServer {
 
         Ok(IdmServerProxyWriteTransaction {
             cred_update_sessions: self.cred_update_sessions.write(),
+            account_recovery: self.account_recovery.write(),
             qs_write,
             sid,
             crypto_policy: &self.crypto_policy,
             webauthn: &self.webauthn,
+            async_tx: self.async_tx.clone(),
+            audit_tx: self.audit_tx.clone(),
+            activity_tx: self.activity_tx.get().cloned(),
+            notify_tx: self.notify_tx.get().cloned(),
+            pending_notifications: Vec::new(),
             oauth2rs: self.oauth2rs.write(),
             applications: self.applications.write(),
+            break_glass: self.break_glass.get(),
+            white_pages: &self.white_pages,
         })
     }
 
@@ -302,9 +406,154 @@ impl IdmServer {
             webauthn: &self.webauthn,
             cred_update_sessions: self.cred_update_sessions.read(),
             crypto_policy: &self.crypto_policy,
+            password_breach: self.password_breach.get(),
+        })
+    }
+
+    /// Subscribe to security notifications. Until this is called no notifications are
+    /// generated.
+    pub fn notifications_subscribe(
+        &self,
+    ) -> Result<Receiver<SecurityNotification>, OperationError> {
+        let (notify_tx, notify_rx) = unbounded();
+        self.notify_tx.set(notify_tx).map_err(|_| {
+            error!("Security notifications already have a subscriber");
+            OperationError::InvalidState
+        })?;
+        Ok(notify_rx)
+    }
+
+    /// Subscribe to the email link messages that need to be delivered. Until this is called
+    /// email link authentication is not offered to accounts.
+    pub fn email_link_subscribe(&self) -> Result<Receiver<EmailLinkMessage>, OperationError> {
+        let (email_link_tx, email_link_rx) = unbounded();
+        self.email_link_tx.set(email_link_tx).map_err(|_| {
+            error!("Email links already have a subscriber");
+            OperationError::InvalidState
+        })?;
+        Ok(email_link_rx)
+    }
+
+    /// Enable break glass authentication of the admin account with the hardware-bound key
+    /// that has this public key, and of the emergency accounts with the recovery codes in
+    /// this file.
+    pub fn break_glass_enable(
+        &self,
+        public_key_pem: Option<&[u8]>,
+        codes_path: Option<&Path>,
+    ) -> Result<&BreakGlass, OperationError> {
+        let break_glass = BreakGlass::new(public_key_pem, codes_path)?;
+        self.break_glass.set(break_glass).map_err(|_| {
+            error!("Break glass authentication is already enabled");
+            OperationError::InvalidState
+        })?;
+        self.break_glass.get().ok_or(OperationError::InvalidState)
+    }
+
+    /// Check new passwords against this dataset of breached passwords, for accounts whose
+    /// account policy requires it.
+    pub fn password_breach_enable(
+        &self,
+        filter: PasswordBreachFilter,
+    ) -> Result<(), OperationError> {
+        self.password_breach.set(filter).map_err(|_| {
+            error!("A breached password dataset is already loaded");
+            OperationError::InvalidState
+        })
+    }
+
+    /// Locate the sources of sign ins and audit events with these databases, and flag sign ins
+    /// from unusual locations.
+    pub fn geoip_enable(&self, geoip: GeoIp) -> Result<(), OperationError> {
+        self.geoip.set(geoip).map_err(|_| {
+            error!("A GeoIP database is already loaded");
+            OperationError::InvalidState
         })
     }
 
+    /// Enable auditing of routine activity, such as successful authentications, token
+    /// issuance, credential changes and modifications made by users. Until this is called
+    /// only failures and other exceptional events are audited.
+    pub fn audit_activity_enable(&self) {
+        if self.activity_tx.set(self.audit_tx.clone()).is_err() {
+            debug!("Activity auditing is already enabled");
+        }
+    }
+
+    /// The number of authentication sessions that have reached each login step since the
+    /// server started.
+    pub fn auth_funnel_metrics(&self) -> AuthFunnelSnapshot {
+        self.auth_metrics.snapshot()
+    }
+
+    /// The operational metrics of this server's backend, such as cache usage, search latency
+    /// and replication state.
+    pub fn server_metrics(&self) -> ServerMetricsSnapshot {
+        self.qs.metrics().snapshot()
+    }
+
+    pub(crate) fn metrics(&self) -> &ServerMetrics {
+        self.qs.metrics()
+    }
+
+    /// Include the items that read transactions have loaded into the shared caches, rather
+    /// than waiting for the next write transaction to do so.
+    pub fn try_quiesce(&self) {
+        self.qs.try_quiesce();
+    }
+
+    /// Submit an audit event that was raised outside of an idm transaction, such as by
+    /// the replication tasks.
+    pub fn submit_audit_event(&self, event: AuditEvent) {
+        if self.audit_tx.send(event).is_err() {
+            error!("Unable to submit audit event to queue");
+        }
+    }
+
+    /// Benchmark the password hashing cost that was selected when this server started. If
+    /// hashing is now significantly slower than the target, the system is likely overloaded
+    /// and authentication will be slow and vulnerable to resource exhaustion.
+    pub fn crypto_policy_self_test(&self) -> SelfTestItem {
+        // How many times slower than the target hashing may be before we warn.
+        const SLOWDOWN_FACTOR: u32 = 4;
+
+        let check = SelfTestCheck::PasswordHashCost;
+
+        let Some(bench) = self.crypto_policy.bench_argon2id() else {
+            return SelfTestItem {
+                check,
+                status: SelfTestStatus::Fail,
+                detail: "unable to perform argon2id benchmark".to_string(),
+            };
+        };
+
+        match self.crypto_policy.target_time() {
+            Some(target) if bench > target * SLOWDOWN_FACTOR => SelfTestItem {
+                check,
+                status: SelfTestStatus::Warn,
+                detail: format!(
+                    "argon2id took {}ms, exceeding the target of {}ms. This system may be overloaded.",
+                    bench.as_millis(),
+                    target.as_millis()
+                ),
+            },
+            Some(target) => SelfTestItem {
+                check,
+                status: SelfTestStatus::Pass,
+                detail: format!(
+                    "argon2id took {}ms with a target of {}ms",
+                    bench.as_millis(),
+                    target.as_millis()
+                ),
+            },
+            None => SelfTestItem {
+                check,
+                status: SelfTestStatus::Pass,
+                detail: format!("argon2id took {}ms", bench.as_millis()),
+            },
+        }
+    }
+
     #[cfg(test)]
     pub(crate) async fn delayed_action(
         &self,
@@ -338,6 +587,21 @@ impl IdmServerAudit {
     pub fn audit_rx(&mut self) -> &mut Receiver<AuditEvent> {
         &mut self.audit_rx
     }
+
+    /// The record of this event as it is stored and exported. If a GeoIP database is loaded,
+    /// this includes where the source of the event is located.
+    pub fn audit_record(&self, event: AuditEvent) -> AuditRecord {
+        let geo = self
+            .geoip
+            .get()
+            .zip(event.source_ip())
+            .and_then(|(geoip, ip)| geoip.locate(ip));
+
+        AuditRecord {
+            geo,
+            ..AuditRecord::from(event)
+        }
+    }
 }
 
 impl IdmServerDelayed {
@@ -358,17 +622,14 @@ impl IdmServerDelayed {
         }
     }
 
-    #[cfg(test)]
-    pub(crate) fn try_recv(&mut self) -> Result<DelayedAction, OperationError> {
-        use core::task::{Context, Poll};
-        use futures::task as futures_task;
-
-        let waker = futures_task::noop_waker();
-        let mut cx = Context::from_waker(&waker);
-        match self.async_rx.poll_recv(&mut cx) {
-            Poll::Pending => Err(OperationError::InvalidState),
-            Poll::Ready(None) => Err(OperationError::QueueDisconnected),
-            Poll::Ready(Some(m)) => Ok(m),
+    #[cfg(any(test, feature = "test"))]
+    pub fn try_recv(&mut self) -> Result<DelayedAction, OperationError> {
+        use tokio::sync::mpsc::error::TryRecvError;
+
+        match self.async_rx.try_recv() {
+            Err(TryRecvError::Empty) => Err(OperationError::InvalidState),
+            Err(TryRecvError::Disconnected) => Err(OperationError::QueueDisconnected),
+            Ok(m) => Ok(m),
         }
     }
 
@@ -389,6 +650,12 @@ pub trait IdmServerTransaction<'a> {
 
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType;
 
+    fn get_async_tx(&self) -> &Sender<DelayedAction>;
+
+    fn get_break_glass(&self) -> Option<&BreakGlass>;
+
+    fn get_white_pages_limiter(&self) -> &WhitePagesLimiter;
+
     /// This is the preferred method to transform and securely verify a token into
     /// an identity that can be used for operations and access enforcement. This
     /// function *is* aware of the various classes of tokens that may exist, and can
@@ -408,18 +675,30 @@ pub trait IdmServerTransaction<'a> {
             client_cert,
             bearer_token,
             basic_authz: _,
+            dpop: _,
+            session_proof,
+            user_agent: _,
         } = client_auth_info;
 
         match (client_cert, bearer_token) {
             (Some(client_cert_info), _) => {
                 self.client_certificate_to_identity(&client_cert_info, ct, source)
             }
-            (None, Some(token)) => match self.validate_and_parse_token_to_token(&token, ct)? {
-                Token::UserAuthToken(uat) => self.process_uat_to_identity(&uat, ct, source),
-                Token::ApiToken(apit, entry) => {
-                    self.process_apit_to_identity(&apit, source, entry, ct)
+            (None, Some(token)) => {
+                if let Some(uat) = self.validate_break_glass_token(&token, ct)? {
+                    return self.process_break_glass_uat_to_identity(&uat, source);
                 }
-            },
+
+                match self.validate_and_parse_token_to_token(&token, ct)? {
+                    Token::UserAuthToken(uat) => {
+                        check_session_binding(&uat, session_proof.as_ref(), ct)?;
+                        self.process_uat_to_identity(&uat, ct, source)
+                    }
+                    Token::ApiToken(apit, entry) => {
+                        self.process_apit_to_identity(&apit, source, entry, ct)
+                    }
+                }
+            }
             (None, None) => {
                 debug!("No client certificate or bearer tokens were supplied");
                 Err(OperationError::NotAuthenticated)
@@ -441,19 +720,31 @@ pub trait IdmServerTransaction<'a> {
             bearer_token,
             source: _,
             basic_authz: _,
+            dpop: _,
+            session_proof,
+            user_agent: _,
         } = client_auth_info;
 
         match (client_cert, bearer_token) {
             (Some(client_cert_info), _) => {
                 self.client_certificate_to_user_auth_token(&client_cert_info, ct)
             }
-            (None, Some(token)) => match self.validate_and_parse_token_to_token(&token, ct)? {
-                Token::UserAuthToken(uat) => Ok(uat),
-                Token::ApiToken(_apit, _entry) => {
-                    warn!("Unable to process non user auth token");
-                    Err(OperationError::NotAuthenticated)
+            (None, Some(token)) => {
+                if let Some(uat) = self.validate_break_glass_token(&token, ct)? {
+                    return Ok(uat);
                 }
-            },
+
+                match self.validate_and_parse_token_to_token(&token, ct)? {
+                    Token::UserAuthToken(uat) => {
+                        check_session_binding(&uat, session_proof.as_ref(), ct)?;
+                        Ok(uat)
+                    }
+                    Token::ApiToken(_apit, _entry) => {
+                        warn!("Unable to process non user auth token");
+                        Err(OperationError::NotAuthenticated)
+                    }
+                }
+            }
             (None, None) => {
                 debug!("No client certificate or bearer tokens were supplied");
                 Err(OperationError::NotAuthenticated)
@@ -518,6 +809,40 @@ pub trait IdmServerTransaction<'a> {
         Err(OperationError::NotAuthenticated)
     }
 
+    /// Break glass sessions are signed by a key that only exists in memory, so they are
+    /// verified before the key objects of the domain are used. Returns `None` if the token
+    /// was not issued by break glass authentication.
+    fn validate_break_glass_token(
+        &mut self,
+        jwsu: &JwsCompact,
+        ct: Duration,
+    ) -> Result<Option<UserAuthToken>, OperationError> {
+        match self.get_break_glass() {
+            Some(break_glass) => break_glass.verify(jwsu, ct),
+            None => Ok(None),
+        }
+    }
+
+    /// A break glass session is not recorded on the admin account, as it must remain usable
+    /// even if the credentials and sessions of the account can't be.
+    fn process_break_glass_uat_to_identity(
+        &mut self,
+        uat: &UserAuthToken,
+        source: Source,
+    ) -> Result<Identity, OperationError> {
+        let entry = self
+            .get_qs_txn()
+            .internal_search_uuid(uat.uuid)
+            .map_err(|e| {
+                admin_error!(?e, "Unable to find the account of the break glass session");
+                e
+            })?;
+
+        security_info!(session_id = ?uat.session_id, "Using break glass session");
+
+        Ok(Identity::from_break_glass(entry, source, uat.session_id))
+    }
+
     fn check_oauth2_account_uuid_valid(
         &mut self,
         uuid: Uuid,
@@ -642,6 +967,51 @@ pub trait IdmServerTransaction<'a> {
 
         // ✅  Session is valid! Start to setup for it to be used.
 
+        if let Some(impersonated_by) = uat.impersonated_by {
+            security_info!(%impersonated_by, uuid = %uat.uuid, session_id = %uat.session_id, "Using impersonation session");
+        }
+
+        // Sessions that have no recorded usage are still being persisted, or predate usage
+        // being recorded.
+        let ct_odt = time::OffsetDateTime::UNIX_EPOCH + ct;
+        let last_used = entry
+            .get_ava_as_credential_usage_map(Attribute::CredentialUsage)
+            .and_then(|usage| usage.get(&(uat.session_id, CredentialFactor::Session)))
+            .map(|usage| usage.last_used);
+
+        if let (Some(idle_timeout), Some(last_used)) = (uat.idle_timeout, last_used) {
+            if ct_odt - last_used >= time::Duration::seconds(idle_timeout as i64) {
+                security_info!(session_id = %uat.session_id, %last_used, "Session has been idle for longer than the idle timeout");
+                return Err(OperationError::SessionExpired);
+            }
+        }
+
+        // Record that the session was used, at most once per granularity period.
+        let granularity = Account::session_use_granularity(uat.idle_timeout);
+        let stale_use = last_used
+            .map(|last_used| ct_odt - last_used >= time::Duration::seconds(granularity as i64))
+            .unwrap_or(false);
+
+        if stale_use {
+            let su = SessionUse {
+                target_uuid: uat.uuid,
+                session_id: uat.session_id,
+                used_at: ct_odt,
+                source: match &source {
+                    Source::Https(ip) | Source::Ldaps(ip) => Some(ip.to_string()),
+                    Source::Internal => None,
+                },
+            };
+            // Failing to record the use of a session must not prevent it being used.
+            if self
+                .get_async_tx()
+                .send(DelayedAction::SessionUse(su))
+                .is_err()
+            {
+                warn!("unable to queue delayed action - session use");
+            }
+        }
+
         let scope = match uat.purpose {
             UatPurpose::ReadOnly => AccessScope::ReadOnly,
             UatPurpose::ReadWrite { expiry: None } => AccessScope::ReadOnly,
@@ -684,13 +1054,15 @@ pub trait IdmServerTransaction<'a> {
         trace!(claims = ?entry.get_ava_set("claim"), "Applied claims");
         */
 
-        Ok(Identity::new(
+        let ident = Identity::new(
             IdentType::User(IdentUser { entry }),
             source,
             uat.session_id,
             scope,
             limits,
-        ))
+        );
+
+        self.apply_white_pages_policy(ident, ct)
     }
 
     #[instrument(level = "debug", skip_all)]
@@ -708,16 +1080,58 @@ pub trait IdmServerTransaction<'a> {
             return Err(OperationError::SessionExpired);
         }
 
+        // Record that the token was used, at most once per granularity period so that each
+        // request doesn't cause a write.
+        let ct_odt = time::OffsetDateTime::UNIX_EPOCH + ct;
+        let recently_used = entry
+            .get_ava_as_credential_usage_map(Attribute::CredentialUsage)
+            .and_then(|usage| usage.get(&(apit.token_id, CredentialFactor::ApiToken)))
+            .map(|usage| {
+                ct_odt - usage.last_used
+                    < time::Duration::seconds(API_TOKEN_LAST_USED_GRANULARITY as i64)
+            })
+            .unwrap_or(false);
+
+        if !recently_used {
+            let atu = ApiTokenUse {
+                target_uuid: apit.account_id,
+                token_id: apit.token_id,
+                used_at: ct_odt,
+                source: match &source {
+                    Source::Https(ip) | Source::Ldaps(ip) => Some(ip.to_string()),
+                    Source::Internal => None,
+                },
+            };
+            // Failing to record the use of a token must not prevent it being used.
+            if self
+                .get_async_tx()
+                .send(DelayedAction::ApiTokenUse(atu))
+                .is_err()
+            {
+                warn!("unable to queue delayed action - api token use");
+            }
+        }
+
         let scope = (&apit.purpose).into();
 
         let limits = Limits::api_token();
-        Ok(Identity::new(
+        let mut ident = Identity::new(
             IdentType::User(IdentUser { entry }),
             source,
             apit.token_id,
             scope,
             limits,
-        ))
+        );
+
+        // The permissions are part of the signed token, so they apply even within the grace
+        // window before the session has replicated.
+        if !apit.permissions.is_empty() {
+            let permissions: Vec<ApiTokenPermission> =
+                apit.permissions.iter().map(|p| p.into()).collect();
+            ident.restrict_to_api_token_permissions(&permissions);
+        }
+
+        Ok(ident)
     }
 
     fn client_cert_info_entry(
@@ -901,13 +1315,56 @@ pub trait IdmServerTransaction<'a> {
 
         // Users via LDAP are always only granted anonymous rights unless
         // they auth with an api-token
-        Ok(Identity::new(
+        let ident = Identity::new(
             IdentType::User(IdentUser { entry: anon_entry }),
             source,
             session_id,
             AccessScope::ReadOnly,
             limits,
-        ))
+        );
+
+        self.apply_white_pages_policy(ident, ct)
+    }
+
+    /// Apply the white pages policy of the domain to an anonymous identity that has been
+    /// granted access to the white pages. Outside of the trusted networks the access is
+    /// removed, and once the rate limit is reached the request is refused.
+    fn apply_white_pages_policy(
+        &mut self,
+        mut ident: Identity,
+        ct: Duration,
+    ) -> Result<Identity, OperationError> {
+        if ident.get_uuid() != Some(UUID_ANONYMOUS)
+            || !ident.is_memberof(UUID_IDM_WHITE_PAGES_READERS)
+        {
+            return Ok(ident);
+        }
+
+        let domain_entry = self
+            .get_qs_txn()
+            .internal_search_uuid(UUID_DOMAIN_INFO)
+            .map_err(|err| {
+                error!(?err, "Unable to search domain info for white pages policy");
+                err
+            })?;
+
+        let policy = WhitePagesPolicy::from_domain_entry(&domain_entry);
+
+        match self
+            .get_white_pages_limiter()
+            .check(&policy, ident.source(), ct)
+        {
+            WhitePagesAccess::Permitted => Ok(ident),
+            WhitePagesAccess::Untrusted => {
+                debug!("Anonymous is outside of the white pages trusted networks");
+                ident.deny_white_pages();
+                Ok(ident)
+            }
+            WhitePagesAccess::RateLimited => {
+                security_info!(source = ?ident.source(), "White pages rate limit reached");
+                Err(OperationError::KG006WhitePagesRateLimited)
+            }
+        }
     }
 
     #[instrument(level = "debug", skip_all)]
@@ -1007,6 +1464,18 @@ impl<'a> IdmServerTransaction<'a> for IdmServerAuthTransaction<'a> {
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType {
         &mut self.qs_read
     }
+
+    fn get_async_tx(&self) -> &Sender<DelayedAction> {
+        &self.async_tx
+    }
+
+    fn get_break_glass(&self) -> Option<&BreakGlass> {
+        self.break_glass
+    }
+
+    fn get_white_pages_limiter(&self) -> &WhitePagesLimiter {
+        self.white_pages
+    }
 }
 
 impl IdmServerAuthTransaction<'_> {
@@ -1032,107 +1501,463 @@ impl IdmServerAuthTransaction<'_> {
         session_write.split_off_lt(&split_at);
         // expired will now be dropped, and can't be used by future sessions.
         session_write.commit();
+
+        let mut challenge_write = self.discoverable_challenges.write();
+        challenge_write.split_off_lt(&split_at);
+        challenge_write.commit();
+
+        if let Some(break_glass) = self.break_glass {
+            break_glass.expire(ct);
+        }
+
+        self.white_pages.expire(ct);
     }
 
-    pub async fn auth(
+    /// Issue a passkey challenge that isn't bound to an account, so that the user can select
+    /// a passkey without giving their username first. The returned id is used to complete the
+    /// authentication with [`Self::auth_discoverable`].
+    pub async fn auth_discoverable_begin(
         &mut self,
-        ae: &AuthEvent,
+        ct: Duration,
+    ) -> Result<(Uuid, RequestChallengeResponse), OperationError> {
+        let challenge_id = uuid_from_duration(ct, self.sid);
+        let challenge = DiscoverableChallenge::new(self.webauthn)?;
+        let chal = challenge.challenge().clone();
+
+        let _session_ticket = self.session_ticket.acquire().await;
+        let mut challenge_write = self.discoverable_challenges.write();
+        if challenge_write.contains_key(&challenge_id) {
+            return Err(OperationError::InvalidSessionState);
+        }
+        challenge_write.insert(challenge_id, challenge);
+        challenge_write.commit();
+
+        Ok((challenge_id, chal))
+    }
+
+    /// Complete a discoverable passkey authentication. The account is identified from the
+    /// passkey, and then an auth session is created for it that validates the passkey as
+    /// the username driven flow would.
+    pub async fn auth_discoverable(
+        &mut self,
+        challenge_id: Uuid,
+        credential: Box<PublicKeyCredential>,
+        issue: AuthIssueSession,
         ct: Duration,
         client_auth_info: ClientAuthInfo,
     ) -> Result<AuthResult, OperationError> {
-        // Match on the auth event, to see what we need to do.
-        match &ae.step {
-            AuthEventStep::Init(init) => {
-                // lperf_segment!("idm::server::auth<Init>", || {
-                // Allocate a session id, based on current time.
-                let sessionid = uuid_from_duration(ct, self.sid);
-
-                // Begin the auth procedure!
-                // Start a read
-                //
-                // Actually we may not need this - at the time we issue the auth-init
-                // we could generate the uat, the nonce and cache hashes in memory,
-                // then this can just be fully without a txn.
-                //
-                // We do need a txn so that we can process/search and claims
-                // or related based on the quality of the provided auth steps
-                //
-                // We *DO NOT* need a write though, because I think that lock outs
-                // and rate limits are *per server* and *in memory* only.
-                //
-                // Check anything needed? Get the current auth-session-id from request
-                // because it associates to the nonce's etc which were all cached.
-                let euuid = self.qs_read.name_to_uuid(init.username.as_str())?;
+        self.auth_metrics.record_initiated();
+
+        // Each challenge can only be used once.
+        let challenge = {
+            let _session_ticket = self.session_ticket.acquire().await;
+            let mut challenge_write = self.discoverable_challenges.write();
+            let challenge = challenge_write.remove(&challenge_id);
+            challenge_write.commit();
+            challenge
+        }
+        .ok_or_else(|| {
+            admin_error!("Invalid Session State (no present discoverable challenge)");
+            OperationError::InvalidSessionState
+        })?;
 
-                // Get the first / single entry we expect here ....
-                let entry = self.qs_read.internal_search_uuid(euuid)?;
+        let denied = |reason: &str| AuthResult {
+            sessionid: challenge_id,
+            state: AuthState::Denied(reason.to_string()),
+        };
 
+        let euuid = match self
+            .webauthn
+            .identify_discoverable_authentication(&credential)
+        {
+            Ok((euuid, _cred_id)) => euuid,
+            Err(e) => {
                 security_info!(
-                    username = %init.username,
-                    issue = ?init.issue,
-                    privileged = ?init.privileged,
-                    uuid = %euuid,
-                    "Initiating Authentication Session",
+                    ?e,
+                    "Unable to identify the account of a discoverable passkey"
                 );
+                return Ok(denied("invalid webauthn authentication"));
+            }
+        };
 
-                // Now, convert the Entry to an account - this gives us some stronger
-                // typing and functionality so we can assess what auth types can
-                // continue, and helps to keep non-needed entry specific data
-                // out of the session tree.
-                let (account, account_policy) =
-                    Account::try_from_entry_with_policy(entry.as_ref(), &mut self.qs_read)?;
+        let entry = match self.qs_read.internal_search_uuid(euuid) {
+            Ok(entry) => entry,
+            Err(OperationError::NoMatchingEntries) => {
+                security_info!(uuid = %euuid, "Discoverable passkey is for an unknown account");
+                return Ok(denied("invalid webauthn authentication"));
+            }
+            Err(err) => return Err(err),
+        };
 
-                trace!(?account.primary);
+        security_info!(
+            ?issue,
+            uuid = %euuid,
+            "Initiating Discoverable Passkey Authentication Session",
+        );
 
-                // Intent to take both trees to write.
-                let _session_ticket = self.session_ticket.acquire().await;
+        let (account, account_policy) =
+            Account::try_from_entry_with_policy(entry.as_ref(), &mut self.qs_read)?;
 
-                // We don't actually check the softlock here. We just initialise
-                // it under the write lock we currently have, so that we can validate
-                // it once we understand what auth mech we will be using.
-                //
-                // NOTE: Very careful use of await here to avoid an issue with write.
-                let _maybe_slock_ref =
-                    account
-                        .primary_cred_uuid_and_policy()
-                        .map(|(cred_uuid, policy)| {
-                            // Acquire the softlock map
-                            //
-                            // We have no issue calling this with .write here, since we
-                            // already hold the session_ticket above.
-                            let mut softlock_write = self.softlocks.write();
-                            let slock_ref: CredSoftLockMutex =
-                                if let Some(slock_ref) = softlock_write.get(&cred_uuid) {
-                                    slock_ref.clone()
-                                } else {
-                                    // Create if not exist, and the cred type supports softlocking.
-                                    let slock = Arc::new(Mutex::new(CredSoftLock::new(policy)));
-                                    softlock_write.insert(cred_uuid, slock.clone());
-                                    slock
-                                };
-                            softlock_write.commit();
-                            slock_ref
-                        });
+        let asd: AuthSessionData = AuthSessionData {
+            account,
+            account_policy,
+            issue,
+            webauthn: self.webauthn,
+            ct,
+            client_auth_info: client_auth_info.clone(),
+            // Discoverable credentials are passkeys, email links and upstream logins don't apply.
+            email_link_tx: None,
+            oidc_upstreams: Vec::new(),
+            unusual_location: false,
+        };
 
-                let asd: AuthSessionData = AuthSessionData {
-                    account,
-                    account_policy,
-                    issue: init.issue,
-                    webauthn: self.webauthn,
-                    ct,
-                    client_auth_info,
-                };
+        let domain_keys = self.qs_read.get_domain_key_object_handle()?;
 
-                let domain_keys = self.qs_read.get_domain_key_object_handle()?;
+        let (auth_session, state) = AuthSession::new_discoverable(asd, challenge, domain_keys);
 
-                let (auth_session, state) = AuthSession::new(asd, init.privileged, domain_keys);
+        let Some(auth_session) = auth_session else {
+            security_info!("Authentication Session Unable to begin");
+            return Ok(AuthResult {
+                sessionid: challenge_id,
+                state,
+            });
+        };
 
-                match auth_session {
-                    Some(auth_session) => {
-                        let mut session_write = self.sessions.write();
-                        if session_write.contains_key(&sessionid) {
-                            // If we have a session of the same id, return an error (despite how
-                            // unlikely this is ...
+        self.auth_metrics
+            .record(&AuthMech::Passkey, AuthFunnelStep::Chosen);
+
+        {
+            let _session_ticket = self.session_ticket.acquire().await;
+            let mut session_write = self.sessions.write();
+            if session_write.contains_key(&challenge_id) {
+                return Err(OperationError::InvalidSessionState);
+            }
+            session_write.insert(challenge_id, Arc::new(Mutex::new(auth_session)));
+            session_write.commit();
+        }
+
+        // The session is now in progress with the passkey handler, so the credential is
+        // validated the same way as any other passkey.
+        let ae = AuthEvent {
+            ident: None,
+            step: AuthEventStep::Cred(AuthEventStepCred {
+                sessionid: challenge_id,
+                cred: AuthCredential::Passkey(credential),
+            }),
+        };
+
+        self.auth(&ae, ct, client_auth_info).await
+    }
+
+    /// The person has returned from an upstream identity provider to the auth session. This
+    /// returns what is needed to exchange the code from the provider for an id token, which
+    /// is then given to [`Self::auth_federated_verify`].
+    pub async fn auth_federated_exchange(
+        &mut self,
+        sessionid: Uuid,
+        state: &str,
+    ) -> Result<OidcUpstreamExchange, OperationError> {
+        let auth_session_ref = self
+            .sessions
+            .read()
+            .get(&sessionid)
+            .cloned()
+            .ok_or_else(|| {
+                admin_error!("Invalid Session State (no present session uuid)");
+                OperationError::InvalidSessionState
+            })?;
+
+        let mut auth_session = auth_session_ref.lock().await;
+        auth_session.federated_exchange(state)
+    }
+
+    /// Verify the id token from the upstream identity provider against the auth session. The
+    /// session then proceeds with an [`AuthCredential::Federated`] step.
+    pub async fn auth_federated_verify(
+        &mut self,
+        sessionid: Uuid,
+        id_token: &str,
+        jwks: &JwkKeySet,
+        ct: Duration,
+    ) -> Result<(), OperationError> {
+        let auth_session_ref = self
+            .sessions
+            .read()
+            .get(&sessionid)
+            .cloned()
+            .ok_or_else(|| {
+                admin_error!("Invalid Session State (no present session uuid)");
+                OperationError::InvalidSessionState
+            })?;
+
+        let mut auth_session = auth_session_ref.lock().await;
+        auth_session.federated_verify(id_token, jwks, ct)
+    }
+
+    /// Issue a challenge to be signed by the break glass key. The returned id is used to
+    /// complete the authentication with [`Self::break_glass_finish`].
+    pub fn break_glass_begin(&mut self, ct: Duration) -> Result<(Uuid, Vec<u8>), OperationError> {
+        let Some(break_glass) = self.break_glass.filter(|break_glass| break_glass.has_key()) else {
+            security_info!("Break glass authentication is not enabled");
+            return Err(OperationError::AccessDenied);
+        };
+
+        Ok(break_glass.challenge(ct))
+    }
+
+    /// Complete a break glass authentication with the signature of the challenge. If the
+    /// signature is valid, a short lived session is issued to the admin account. This must
+    /// be audited, so if the audit event can't be submitted no session is issued.
+    pub fn break_glass_finish(
+        &mut self,
+        challenge_id: Uuid,
+        signature: &[u8],
+        ct: Duration,
+        source: Source,
+    ) -> Result<JwsCompact, OperationError> {
+        let Some(break_glass) = self.break_glass else {
+            security_info!("Break glass authentication is not enabled");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let time = time::OffsetDateTime::UNIX_EPOCH + ct;
+
+        if let Err(err) = break_glass.verify_challenge(challenge_id, signature, ct) {
+            security_error!(?err, "Break glass authentication denied");
+            if self
+                .audit_tx
+                .send(AuditEvent::BreakGlassDenied {
+                    source: source.into(),
+                    account: None,
+                    time,
+                })
+                .is_err()
+            {
+                error!("Unable to submit audit event to queue");
+            }
+            return Err(err);
+        }
+
+        self.break_glass_issue(break_glass, UUID_ADMIN, ct, source)
+    }
+
+    /// Authenticate an emergency account with one of the recovery codes that were generated
+    /// for it offline. Each code can only be used once. As with a break glass key, a short
+    /// lived session is issued only if the audit event can be submitted.
+    pub fn break_glass_code(
+        &mut self,
+        account: &str,
+        code: &str,
+        ct: Duration,
+        source: Source,
+    ) -> Result<JwsCompact, OperationError> {
+        let Some(break_glass) = self.break_glass else {
+            security_info!("Break glass authentication is not enabled");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let time = time::OffsetDateTime::UNIX_EPOCH + ct;
+
+        if let Err(err) = break_glass.verify_code(account, code) {
+            security_error!(?err, %account, "Break glass recovery code denied");
+            if self
+                .audit_tx
+                .send(AuditEvent::BreakGlassDenied {
+                    source: source.into(),
+                    account: Some(account.to_string()),
+                    time,
+                })
+                .is_err()
+            {
+                error!("Unable to submit audit event to queue");
+            }
+            return Err(err);
+        }
+
+        let uuid = self.qs_read.name_to_uuid(account).map_err(|err| {
+            error!(?err, %account, "Unable to find the emergency account");
+            err
+        })?;
+
+        self.break_glass_issue(break_glass, uuid, ct, source)
+    }
+
+    fn break_glass_issue(
+        &mut self,
+        break_glass: &BreakGlass,
+        uuid: Uuid,
+        ct: Duration,
+        source: Source,
+    ) -> Result<JwsCompact, OperationError> {
+        let time = time::OffsetDateTime::UNIX_EPOCH + ct;
+
+        let entry = self.qs_read.internal_search_uuid(uuid)?;
+        if !entry.attribute_equality(Attribute::Class, &EntryClass::Account.into()) {
+            error!(%uuid, "Break glass sessions can only be issued to accounts");
+            return Err(OperationError::NotAuthenticated);
+        }
+
+        let spn = entry
+            .get_ava_single_proto_string(Attribute::Spn)
+            .ok_or(OperationError::MissingAttribute(Attribute::Spn))?;
+        let displayname = entry
+            .get_ava_single_utf8(Attribute::DisplayName)
+            .map(str::to_string)
+            .unwrap_or_else(|| spn.clone());
+
+        let (uat, token) = break_glass.issue(uuid, spn.clone(), displayname, ct)?;
+
+        self.audit_tx
+            .send(AuditEvent::BreakGlassAuthenticated {
+                source: source.into(),
+                uuid,
+                spn,
+                session_id: uat.session_id,
+                expiry: uat.expiry.unwrap_or(time),
+                time,
+            })
+            .map_err(|_| {
+                error!("Unable to audit break glass authentication, refusing to issue a session");
+                OperationError::InvalidState
+            })?;
+
+        security_critical!(
+            %uuid,
+            session_id = ?uat.session_id,
+            expiry = ?uat.expiry,
+            "Break glass session issued"
+        );
+
+        Ok(token)
+    }
+
+    pub async fn auth(
+        &mut self,
+        ae: &AuthEvent,
+        ct: Duration,
+        client_auth_info: ClientAuthInfo,
+    ) -> Result<AuthResult, OperationError> {
+        // Match on the auth event, to see what we need to do.
+        match &ae.step {
+            AuthEventStep::Init(init) => {
+                // Count every attempt, including those for accounts that don't exist.
+                self.auth_metrics.record_initiated();
+
+                // lperf_segment!("idm::server::auth<Init>", || {
+                // Allocate a session id, based on current time.
+                let sessionid = uuid_from_duration(ct, self.sid);
+
+                // Begin the auth procedure!
+                // Start a read
+                //
+                // Actually we may not need this - at the time we issue the auth-init
+                // we could generate the uat, the nonce and cache hashes in memory,
+                // then this can just be fully without a txn.
+                //
+                // We do need a txn so that we can process/search and claims
+                // or related based on the quality of the provided auth steps
+                //
+                // We *DO NOT* need a write though, because I think that lock outs
+                // and rate limits are *per server* and *in memory* only.
+                //
+                // Check anything needed? Get the current auth-session-id from request
+                // because it associates to the nonce's etc which were all cached.
+                let euuid = self.qs_read.name_to_uuid(init.username.as_str())?;
+
+                // Get the first / single entry we expect here ....
+                let entry = self.qs_read.internal_search_uuid(euuid)?;
+
+                security_info!(
+                    username = %init.username,
+                    issue = ?init.issue,
+                    privileged = ?init.privileged,
+                    uuid = %euuid,
+                    "Initiating Authentication Session",
+                );
+
+                // Now, convert the Entry to an account - this gives us some stronger
+                // typing and functionality so we can assess what auth types can
+                // continue, and helps to keep non-needed entry specific data
+                // out of the session tree.
+                let (account, account_policy) =
+                    Account::try_from_entry_with_policy(entry.as_ref(), &mut self.qs_read)?;
+
+                trace!(?account.primary);
+
+                // Intent to take both trees to write.
+                let _session_ticket = self.session_ticket.acquire().await;
+
+                // We don't actually check the softlock here. We just initialise
+                // it under the write lock we currently have, so that we can validate
+                // it once we understand what auth mech we will be using.
+                //
+                // NOTE: Very careful use of await here to avoid an issue with write.
+                let maybe_slock_ref =
+                    account
+                        .primary_cred_uuid_and_policy()
+                        .map(|(cred_uuid, policy)| {
+                            let policy = account_policy.softlock_policy(policy);
+                            // Acquire the softlock map
+                            //
+                            // We have no issue calling this with .write here, since we
+                            // already hold the session_ticket above.
+                            let mut softlock_write = self.softlocks.write();
+                            let slock_ref: CredSoftLockMutex = if let Some(slock_ref) =
+                                softlock_write.get(&cred_uuid)
+                            {
+                                slock_ref.clone()
+                            } else {
+                                // Create if not exist, and the cred type supports softlocking.
+                                let slock = Arc::new(Mutex::new(CredSoftLock::new(policy.clone())));
+                                softlock_write.insert(cred_uuid, slock.clone());
+                                slock
+                            };
+                            softlock_write.commit();
+                            (slock_ref, policy)
+                        });
+
+                // The account policy may have changed since the softlock was created.
+                if let Some((slock_ref, policy)) = maybe_slock_ref {
+                    slock_ref.lock().await.set_policy(policy);
+                }
+
+                let oidc_upstreams = oidc_upstreams_load(&mut self.qs_read)?;
+
+                let unusual_location = self
+                    .geoip
+                    .filter(|geoip| geoip.step_up())
+                    .and_then(|geoip| geoip.assess(euuid, &client_auth_info.source, ct))
+                    .inspect(|anomaly| {
+                        security_info!(
+                            uuid = %euuid,
+                            ?anomaly,
+                            "Sign in is from an unusual location, requiring multi-factor authentication"
+                        );
+                    })
+                    .is_some();
+
+                let asd: AuthSessionData = AuthSessionData {
+                    account,
+                    account_policy,
+                    issue: init.issue,
+                    webauthn: self.webauthn,
+                    ct,
+                    client_auth_info,
+                    email_link_tx: self.email_link_tx.clone(),
+                    oidc_upstreams,
+                    unusual_location,
+                };
+
+                let domain_keys = self.qs_read.get_domain_key_object_handle()?;
+
+                let (auth_session, state) = AuthSession::new(asd, init.privileged, domain_keys);
+
+                match auth_session {
+                    Some(auth_session) => {
+                        let mut session_write = self.sessions.write();
+                        if session_write.contains_key(&sessionid) {
+                            // If we have a session of the same id, return an error (despite how
+                            // unlikely this is ...
                             Err(OperationError::InvalidSessionState)
                         } else {
                             session_write.insert(sessionid, Arc::new(Mutex::new(auth_session)));
@@ -1164,9 +1989,9 @@ impl IdmServerAuthTransaction<'_> {
                 let mut auth_session = auth_session_ref.lock().await;
 
                 // Indicate to the session which auth mech we now want to proceed with.
-                let auth_result = auth_session.start_session(&mech.mech);
+                let auth_result = auth_session.start_session(&mech.mech, ct);
 
-                let is_valid = match auth_session.get_credential_uuid()? {
+                let (is_valid, locked_until) = match auth_session.get_credential_uuid()? {
                     Some(cred_uuid) => {
                         // From the auth_session, determine if the current account
                         // credential that we are using has become softlocked or not.
@@ -1176,13 +2001,13 @@ impl IdmServerAuthTransaction<'_> {
                             // Apply the current time.
                             slock.apply_time_step(ct);
                             // Now check the results
-                            slock.is_valid()
+                            (slock.is_valid(), slock.locked_until())
                         } else {
                             trace!("slock not found");
-                            false
+                            (false, None)
                         }
                     }
-                    None => true,
+                    None => (true, None),
                 };
 
                 if is_valid {
@@ -1190,8 +2015,14 @@ impl IdmServerAuthTransaction<'_> {
                 } else {
                     // Fail the session
                     trace!("lock step begin");
-                    auth_session.end_session("Account is temporarily locked")
+                    auth_session.end_session_softlocked(locked_until)
                 }
+                .inspect(|aus| {
+                    self.auth_metrics.record(&mech.mech, AuthFunnelStep::Chosen);
+                    if matches!(aus, AuthState::Denied(_)) {
+                        self.auth_metrics.record(&mech.mech, AuthFunnelStep::Denied);
+                    }
+                })
                 .map(|aus| AuthResult {
                     sessionid: mech.sessionid,
                     state: aus,
@@ -1214,6 +2045,15 @@ impl IdmServerAuthTransaction<'_> {
 
                 let mut auth_session = auth_session_ref.lock().await;
 
+                // The mechanism must be taken before the credentials are validated, as the
+                // session no longer holds it once it has completed.
+                let funnel_mech = auth_session.current_mech();
+                if let Some(mech) = &funnel_mech {
+                    if auth_session.record_first_submission() {
+                        self.auth_metrics.record(mech, AuthFunnelStep::Submitted);
+                    }
+                }
+
                 let maybe_slock_ref = match auth_session.get_credential_uuid()? {
                     Some(cred_uuid) => {
                         let softlock_read = self.softlocks.read();
@@ -1241,10 +2081,18 @@ impl IdmServerAuthTransaction<'_> {
                 };
 
                 if is_valid {
+                    let account_uuid = auth_session.account_uuid();
+                    let account_spn = auth_session.account_spn().to_string();
+                    let source = auth_session.source().clone();
+                    let notify_tx = &self.notify_tx;
+                    let activity_tx = &self.activity_tx;
+                    let audit_tx = &self.audit_tx;
+                    let geoip = self.geoip;
+
                     // Process the credentials here as required.
                     // Basically throw them at the auth_session and see what
                     // falls out.
-                    auth_session
+                    let res = auth_session
                         .validate_creds(
                             &creds.cred,
                             ct,
@@ -1256,18 +2104,106 @@ impl IdmServerAuthTransaction<'_> {
                         .inspect(|aus| {
                             // Inspect the result:
                             // if it was a failure, we need to inc the softlock.
-                            if let AuthState::Denied(_) = aus {
-                                // Update it.
-                                if let Some(ref mut slock) = maybe_slock {
-                                    slock.record_failure(ct);
+                            match aus {
+                                AuthState::Denied(_) => {
+                                    // Update it.
+                                    if let Some(ref mut slock) = maybe_slock {
+                                        slock.record_failure(ct);
+
+                                        // Only tell the account owner about locks that will
+                                        // persist, not the short delays between attempts.
+                                        if let Some(until) = slock.locked_until() {
+                                            if until >= ct + SOFTLOCK_NOTIFICATION_THRESHOLD {
+                                                send_notification(
+                                                    notify_tx,
+                                                    SecurityNotification::AccountLocked {
+                                                        uuid: account_uuid,
+                                                        until: time::OffsetDateTime::UNIX_EPOCH
+                                                            + until,
+                                                        time: time::OffsetDateTime::UNIX_EPOCH + ct,
+                                                    },
+                                                );
+                                            }
+                                        }
+                                    }
+                                }
+                                AuthState::Success(..) => {
+                                    let (location, anomaly) = geoip
+                                        .map(|geoip| geoip.record(account_uuid, &source, ct))
+                                        .unwrap_or_default();
+                                    if let Some(anomaly) = anomaly {
+                                        if audit_tx
+                                            .send(AuditEvent::SessionAnomaly {
+                                                source: source.clone().into(),
+                                                uuid: account_uuid,
+                                                spn: account_spn.clone(),
+                                                anomaly: anomaly.clone(),
+                                                time: time::OffsetDateTime::UNIX_EPOCH + ct,
+                                            })
+                                            .is_err()
+                                        {
+                                            error!("Unable to submit audit event to queue");
+                                        }
+                                        send_notification(
+                                            notify_tx,
+                                            SecurityNotification::UnusualSignIn {
+                                                uuid: account_uuid,
+                                                source: source.clone(),
+                                                anomaly,
+                                                time: time::OffsetDateTime::UNIX_EPOCH + ct,
+                                            },
+                                        );
+                                    }
+                                    send_activity(
+                                        activity_tx,
+                                        AuditEvent::AuthenticationSucceeded {
+                                            source: source.clone().into(),
+                                            uuid: account_uuid,
+                                            spn: account_spn.clone(),
+                                            time: time::OffsetDateTime::UNIX_EPOCH + ct,
+                                        },
+                                    );
+                                    send_notification(
+                                        notify_tx,
+                                        SecurityNotification::SessionCreated {
+                                            uuid: account_uuid,
+                                            source: source.clone(),
+                                            location,
+                                            time: time::OffsetDateTime::UNIX_EPOCH + ct,
+                                        },
+                                    );
                                 }
+                                _ => {}
                             };
-                        })
+                        });
+
+                    // If this failure has locked the credential, tell the user now rather
+                    // than on their next attempt.
+                    let locked_until = maybe_slock.as_ref().and_then(|slock| slock.locked_until());
+                    match res {
+                        Ok(AuthState::Denied(_)) if locked_until.is_some() => {
+                            auth_session.end_session_softlocked(locked_until)
+                        }
+                        res => res,
+                    }
                 } else {
                     // Fail the session
                     trace!("lock step cred");
-                    auth_session.end_session("Account is temporarily locked")
+                    let locked_until = maybe_slock.as_ref().and_then(|slock| slock.locked_until());
+                    auth_session.end_session_softlocked(locked_until)
                 }
+                .inspect(|aus| {
+                    let step = match aus {
+                        AuthState::Success(..) | AuthState::CredentialUpdateRequired(_) => {
+                            AuthFunnelStep::Succeeded
+                        }
+                        AuthState::Denied(_) => AuthFunnelStep::Denied,
+                        AuthState::Choose(_) | AuthState::Continue(_) => return,
+                    };
+                    if let Some(mech) = &funnel_mech {
+                        self.auth_metrics.record(mech, step);
+                    }
+                })
                 .map(|aus| AuthResult {
                     sessionid: creds.sessionid,
                     state: aus,
@@ -1281,7 +2217,7 @@ impl IdmServerAuthTransaction<'_> {
         id: Uuid,
         cleartext: &str,
         ct: Duration,
-    ) -> Result<Option<Account>, OperationError> {
+    ) -> Result<Option<(Account, ResolvedAccountPolicy)>, OperationError> {
         let entry = match self.qs_read.internal_search_uuid(id) {
             Ok(entry) => entry,
             Err(e) => {
@@ -1298,13 +2234,16 @@ impl IdmServerAuthTransaction<'_> {
             return Ok(None);
         }
 
+        // A unix password that account policy no longer allows can't be used.
+        let ucred = account
+            .unix_extn()
+            .and_then(|extn| extn.ucred())
+            .filter(|_| acp.allow_unix_password());
+
         let cred = if acp.allow_primary_cred_fallback() == Some(true) {
-            account
-                .unix_extn()
-                .and_then(|extn| extn.ucred())
-                .or_else(|| account.primary())
+            ucred.or_else(|| account.primary())
         } else {
-            account.unix_extn().and_then(|extn| extn.ucred())
+            ucred
         };
 
         let (cred, cred_id, cred_slock_policy) = match cred {
@@ -1316,7 +2255,7 @@ impl IdmServerAuthTransaction<'_> {
                 }
                 return Ok(None);
             }
-            Some(cred) => (cred, cred.uuid, cred.softlock_policy()),
+            Some(cred) => (cred, cred.uuid, acp.softlock_policy(cred.softlock_policy())),
         };
 
         // The credential should only ever be a password
@@ -1332,7 +2271,7 @@ impl IdmServerAuthTransaction<'_> {
             } else {
                 let _session_ticket = self.session_ticket.acquire().await;
                 let mut softlock_write = self.softlocks.write();
-                let slock = Arc::new(Mutex::new(CredSoftLock::new(cred_slock_policy)));
+                let slock = Arc::new(Mutex::new(CredSoftLock::new(cred_slock_policy.clone())));
                 softlock_write.insert(cred_id, slock.clone());
                 softlock_write.commit();
                 slock
@@ -1341,6 +2280,7 @@ impl IdmServerAuthTransaction<'_> {
 
         let mut slock = slock_ref.lock().await;
 
+        slock.set_policy(cred_slock_policy);
         slock.apply_time_step(ct);
 
         if !slock.is_valid() {
@@ -1374,7 +2314,7 @@ impl IdmServerAuthTransaction<'_> {
                 })?;
         }
 
-        Ok(Some(account))
+        Ok(Some((account, acp)))
     }
 
     pub async fn auth_unix(
@@ -1382,10 +2322,27 @@ impl IdmServerAuthTransaction<'_> {
         uae: &UnixUserAuthEvent,
         ct: Duration,
     ) -> Result<Option<UnixUserToken>, OperationError> {
-        Ok(self
+        let Some((account, account_policy)) = self
             .auth_with_unix_pass(uae.target, &uae.cleartext, ct)
             .await?
-            .and_then(|acc| acc.to_unixusertoken(ct).ok()))
+        else {
+            return Ok(None);
+        };
+
+        if !check_login_host(&account_policy, &uae.ident, &mut self.qs_read)? {
+            security_info!(
+                "Account policy does not allow {} to log in to this host",
+                account.spn
+            );
+            return Ok(None);
+        }
+
+        let hbac = load_unix_hbac_policy(&account, &mut self.qs_read)?;
+        let login_hosts = load_unix_login_hosts(&account_policy, &mut self.qs_read)?;
+
+        Ok(account
+            .to_unixusertoken(&account_policy, hbac, login_hosts, ct)
+            .ok())
     }
 
     pub async fn auth_ldap(
@@ -1432,7 +2389,7 @@ impl IdmServerAuthTransaction<'_> {
                 .await?;
 
             match auth {
-                Some(account) => {
+                Some((account, _)) => {
                     let session_id = Uuid::new_v4();
                     security_info!(
                         "Starting session {} for {} {}",
@@ -1459,6 +2416,8 @@ impl IdmServerAuthTransaction<'_> {
     ) -> Result<Option<LdapBoundToken>, OperationError> {
         match self.validate_and_parse_token_to_token(&lae.token, ct)? {
             Token::UserAuthToken(uat) => {
+                // A session that is bound to a browser's key can't present a proof over ldap.
+                check_session_binding(&uat, None, ct)?;
                 let spn = uat.spn.clone();
                 Ok(Some(LdapBoundToken {
                     session_id: uat.session_id,
@@ -1480,6 +2439,113 @@ impl IdmServerAuthTransaction<'_> {
         }
     }
 
+    /// Only an identity that could reset the primary credential of the target may view or
+    /// clear its softlock. Returns the uuid of the credential, if the target has one.
+    fn softlock_target_credential(
+        &mut self,
+        ident: &Identity,
+        target: Uuid,
+    ) -> Result<Option<Uuid>, OperationError> {
+        // Effective permission assumes you are in rw.
+        if ident.access_scope() != AccessScope::ReadWrite {
+            security_access!("identity access scope is not permitted to modify");
+            security_access!("denied ❌");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let entry = self.qs_read.internal_search_uuid(target)?;
+
+        let effective_perms = self
+            .qs_read
+            .get_accesscontrols()
+            .effective_permission_check(
+                ident,
+                Some(btreeset![Attribute::PrimaryCredential]),
+                &[entry.clone()],
+            )?;
+
+        let eperm = effective_perms.first().ok_or_else(|| {
+            error!("Effective Permission check returned no results");
+            OperationError::InvalidState
+        })?;
+
+        if eperm.target != target {
+            error!("Effective Permission check target differs from requested entry uuid");
+            return Err(OperationError::InvalidEntryState);
+        }
+
+        let can_reset_primary_cred = [&eperm.search, &eperm.modify_pres, &eperm.modify_rem]
+            .into_iter()
+            .all(|access| match access {
+                Access::Denied => false,
+                Access::Grant => true,
+                Access::Allow(attrs) => attrs.contains(&Attribute::PrimaryCredential),
+            });
+
+        if !can_reset_primary_cred {
+            security_access!("identity is not permitted to modify the primary credential");
+            security_access!("denied ❌");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let account = Account::try_from_entry_ro(entry.as_ref(), &mut self.qs_read)?;
+
+        Ok(account
+            .primary_cred_uuid_and_policy()
+            .map(|(cred_uuid, _policy)| cred_uuid))
+    }
+
+    /// The softlock of the target's primary credential. Softlocks are held in memory, so
+    /// this only reflects authentication attempts made to this server.
+    pub async fn credential_softlock_status(
+        &mut self,
+        ident: &Identity,
+        target: Uuid,
+        ct: Duration,
+    ) -> Result<CredentialSoftLockStatus, OperationError> {
+        let maybe_slock_ref = self
+            .softlock_target_credential(ident, target)?
+            .and_then(|cred_uuid| self.softlocks.read().get(&cred_uuid).cloned());
+
+        let Some(slock_ref) = maybe_slock_ref else {
+            return Ok(CredentialSoftLockStatus {
+                failures: 0,
+                unlock_at: None,
+                locked_out: false,
+            });
+        };
+
+        let mut slock = slock_ref.lock().await;
+        slock.apply_time_step(ct);
+
+        Ok(CredentialSoftLockStatus {
+            failures: slock.failure_count() as u64,
+            unlock_at: slock
+                .unlock_at()
+                .map(|unlock_at| time::OffsetDateTime::UNIX_EPOCH + unlock_at),
+            locked_out: slock.locked_until().is_some(),
+        })
+    }
+
+    /// Clear the softlock of the target's primary credential, allowing it to be used
+    /// immediately.
+    pub async fn credential_softlock_clear(
+        &mut self,
+        ident: &Identity,
+        target: Uuid,
+    ) -> Result<(), OperationError> {
+        let maybe_slock_ref = self
+            .softlock_target_credential(ident, target)?
+            .and_then(|cred_uuid| self.softlocks.read().get(&cred_uuid).cloned());
+
+        if let Some(slock_ref) = maybe_slock_ref {
+            slock_ref.lock().await.reset();
+            security_info!(%target, "Cleared credential softlock");
+        }
+
+        Ok(())
+    }
+
     pub fn commit(self) -> Result<(), OperationError> {
         Ok(())
     }
@@ -1491,6 +2557,18 @@ impl<'a> IdmServerTransaction<'a> for IdmServerProxyReadTransaction<'a> {
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType {
         &mut self.qs_read
     }
+
+    fn get_async_tx(&self) -> &Sender<DelayedAction> {
+        &self.async_tx
+    }
+
+    fn get_break_glass(&self) -> Option<&BreakGlass> {
+        self.break_glass
+    }
+
+    fn get_white_pages_limiter(&self) -> &WhitePagesLimiter {
+        self.white_pages
+    }
 }
 
 fn gen_password_mod(
@@ -1524,6 +2602,12 @@ fn gen_password_upgrade_mod(
 
 impl IdmServerProxyReadTransaction<'_> {
     pub fn jws_public_jwk(&mut self, key_id: &str) -> Result<Jwk, OperationError> {
+        if let Some(break_glass) = self.break_glass {
+            if let Some(jwk) = break_glass.public_jwk(key_id)? {
+                return Ok(jwk);
+            }
+        }
+
         self.qs_read
             .get_key_providers()
             .get_key_object_handle(UUID_DOMAIN_INFO)
@@ -1557,16 +2641,21 @@ impl IdmServerProxyReadTransaction<'_> {
         uute: &UnixUserTokenEvent,
         ct: Duration,
     ) -> Result<UnixUserToken, OperationError> {
-        let account = self
+        let (account, account_policy) = self
             .qs_read
             .impersonate_search_uuid(uute.target, &uute.ident)
-            .and_then(|account_entry| Account::try_from_entry_ro(&account_entry, &mut self.qs_read))
+            .and_then(|account_entry| {
+                Account::try_from_entry_with_policy(&account_entry, &mut self.qs_read)
+            })
             .map_err(|e| {
                 admin_error!("Failed to start unix user token -> {:?}", e);
                 e
             })?;
 
-        account.to_unixusertoken(ct)
+        let hbac = load_unix_hbac_policy(&account, &mut self.qs_read)?;
+        let login_hosts = load_unix_login_hosts(&account_policy, &mut self.qs_read)?;
+
+        account.to_unixusertoken(&account_policy, hbac, login_hosts, ct)
     }
 
     pub fn get_unixgrouptoken(
@@ -1602,6 +2691,26 @@ impl IdmServerProxyReadTransaction<'_> {
         account.to_credentialstatus()
     }
 
+    /// The account policy that applies to an account, resolved from the groups it is a
+    /// member of.
+    pub fn get_account_policy(
+        &mut self,
+        rape: &ReadAccountPolicyEvent,
+    ) -> Result<EffectiveAccountPolicy, OperationError> {
+        let (_account, account_policy) = self
+            .qs_read
+            .impersonate_search_uuid(rape.target, &rape.ident)
+            .and_then(|account_entry| {
+                Account::try_from_entry_with_policy(&account_entry, &mut self.qs_read)
+            })
+            .map_err(|e| {
+                admin_error!("Failed to search account {:?}", e);
+                e
+            })?;
+
+        Ok(account_policy.to_effective_policy())
+    }
+
     pub fn get_backup_codes(
         &mut self,
         rbce: &ReadBackupCodeEvent,
@@ -1627,6 +2736,18 @@ impl<'a> IdmServerTransaction<'a> for IdmServerProxyWriteTransaction<'a> {
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType {
         &mut self.qs_write
     }
+
+    fn get_async_tx(&self) -> &Sender<DelayedAction> {
+        &self.async_tx
+    }
+
+    fn get_break_glass(&self) -> Option<&BreakGlass> {
+        self.break_glass
+    }
+
+    fn get_white_pages_limiter(&self) -> &WhitePagesLimiter {
+        self.white_pages
+    }
 }
 
 impl IdmServerProxyWriteTransaction<'_> {
@@ -1655,6 +2776,13 @@ impl IdmServerProxyWriteTransaction<'_> {
             ]));
         }
 
+        if let Some(term) = password_denylist_match(self.qs_write.pw_denylist(), cleartext) {
+            security_info!(?term, "Password contains a denied term, rejecting");
+            return Err(OperationError::PasswordQuality(vec![
+                PasswordFeedback::DenyListed(term.to_string()),
+            ]));
+        }
+
         // does the password pass zxcvbn?
 
         let entropy = zxcvbn::zxcvbn(cleartext, related_inputs).map_err(|e| {
@@ -1783,12 +2911,12 @@ impl IdmServerProxyWriteTransaction<'_> {
         pce: &UnixPasswordChangeEvent,
     ) -> Result<(), OperationError> {
         // Get the account
-        let account = self
+        let (account, account_policy) = self
             .qs_write
             .internal_search_uuid(pce.target)
             .and_then(|account_entry| {
                 // Assert the account is unix and valid.
-                Account::try_from_entry_rw(&account_entry, &mut self.qs_write)
+                Account::try_from_entry_with_policy(&account_entry, &mut self.qs_write)
             })
             .map_err(|e| {
                 admin_error!("Failed to start set unix account password {:?}", e);
@@ -1802,6 +2930,11 @@ impl IdmServerProxyWriteTransaction<'_> {
             ));
         }
 
+        if !account_policy.allow_unix_password() {
+            security_info!("Account policy does not allow a UNIX password");
+            return Err(OperationError::AccessDenied);
+        }
+
         // Deny the change if the account is anonymous!
         if account.is_anonymous() {
             trace!("Unable to use anonymous to change UNIX account password");
@@ -2093,7 +3226,11 @@ impl IdmServerProxyWriteTransaction<'_> {
         info!(session_id = %asr.session_id, "Persisting auth session");
 
         // modify the account to put the session onto it.
-        let modlist = ModifyList::new_append(Attribute::UserAuthTokenSession, session);
+        let mut mods = vec![Modify::Present(Attribute::UserAuthTokenSession, session)];
+
+        mods.extend(self.credential_usage_mods(asr)?);
+
+        let modlist = ModifyList::new_list(mods);
 
         self.qs_write
             .internal_modify(
@@ -2107,6 +3244,98 @@ impl IdmServerProxyWriteTransaction<'_> {
         // Done!
     }
 
+    /// Record the use of each factor of the credential that created this session, and where
+    /// the session was created from. At the same time usage of credentials and sessions that
+    /// no longer exist on the account is removed.
+    fn credential_usage_mods(
+        &mut self,
+        asr: &AuthSessionRecord,
+    ) -> Result<Vec<Modify>, OperationError> {
+        let entry = self.qs_write.internal_search_uuid(asr.target_uuid)?;
+
+        let live_cred_ids: BTreeSet<Uuid> = entry
+            .get_ava_single_credential(Attribute::PrimaryCredential)
+            .map(|cred| cred.uuid)
+            .into_iter()
+            .chain(
+                entry
+                    .get_ava_passkeys(Attribute::PassKeys)
+                    .into_iter()
+                    .flat_map(|pks| pks.keys().copied()),
+            )
+            .chain(
+                entry
+                    .get_ava_attestedpasskeys(Attribute::AttestedPasskeys)
+                    .into_iter()
+                    .flat_map(|pks| pks.keys().copied()),
+            )
+            .chain(
+                entry
+                    .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+                    .into_iter()
+                    .flat_map(|tokens| tokens.keys().copied()),
+            )
+            .chain(
+                entry
+                    .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+                    .into_iter()
+                    .flat_map(|sessions| sessions.keys().copied()),
+            )
+            .collect();
+
+        let usage = entry.get_ava_as_credential_usage_map(Attribute::CredentialUsage);
+
+        let stale_cred_ids: BTreeSet<Uuid> = usage
+            .into_iter()
+            .flat_map(|usage| usage.keys())
+            .map(|(cred_id, _)| *cred_id)
+            .filter(|cred_id| !live_cred_ids.contains(cred_id))
+            .collect();
+
+        let mut mods: Vec<_> = stale_cred_ids
+            .into_iter()
+            .map(|cred_id| Modify::Removed(Attribute::CredentialUsage, PartialValue::Uuid(cred_id)))
+            .collect();
+
+        for factor in asr.factors.iter() {
+            let use_count = usage
+                .and_then(|usage| usage.get(&(asr.cred_id, factor.clone())))
+                .map(|usage| usage.use_count)
+                .unwrap_or_default()
+                .saturating_add(1);
+
+            mods.push(Modify::Present(
+                Attribute::CredentialUsage,
+                Value::CredentialUsage(
+                    asr.cred_id,
+                    factor.clone(),
+                    CredentialUsage {
+                        last_used: asr.issued_at,
+                        use_count,
+                        source: asr.source.clone(),
+                        user_agent: None,
+                    },
+                ),
+            ));
+        }
+
+        mods.push(Modify::Present(
+            Attribute::CredentialUsage,
+            Value::CredentialUsage(
+                asr.session_id,
+                CredentialFactor::Session,
+                CredentialUsage {
+                    last_used: asr.issued_at,
+                    use_count: 1,
+                    source: asr.source.clone(),
+                    user_agent: asr.user_agent.clone(),
+                },
+            ),
+        ));
+
+        Ok(mods)
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub fn process_delayedaction(
         &mut self,
@@ -2119,7 +3348,88 @@ impl IdmServerProxyWriteTransaction<'_> {
             DelayedAction::WebauthnCounterIncrement(wci) => self.process_webauthncounterinc(wci),
             DelayedAction::BackupCodeRemoval(bcr) => self.process_backupcoderemoval(bcr),
             DelayedAction::AuthSessionRecord(asr) => self.process_authsessionrecord(asr),
+            DelayedAction::ApiTokenUse(atu) => self.process_apitokenuse(atu),
+            DelayedAction::SessionUse(su) => self.process_sessionuse(su),
+        }
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) fn process_sessionuse(&mut self, su: &SessionUse) -> Result<(), OperationError> {
+        let entry = self.qs_write.internal_search_uuid(su.target_uuid)?;
+
+        // The session usage is removed when the session is.
+        let Some(usage) = entry
+            .get_ava_as_credential_usage_map(Attribute::CredentialUsage)
+            .and_then(|usage| usage.get(&(su.session_id, CredentialFactor::Session)))
+        else {
+            debug!(session_id = %su.session_id, "Session no longer exists, not recording use");
+            return Ok(());
+        };
+
+        let modlist = ModifyList::new_list(vec![Modify::Present(
+            Attribute::CredentialUsage,
+            Value::CredentialUsage(
+                su.session_id,
+                CredentialFactor::Session,
+                CredentialUsage {
+                    last_used: su.used_at,
+                    use_count: usage.use_count.saturating_add(1),
+                    source: su.source.clone(),
+                    // The device is the one that created the session.
+                    user_agent: usage.user_agent.clone(),
+                },
+            ),
+        )]);
+
+        self.qs_write
+            .internal_modify_uuid(su.target_uuid, &modlist)
+            .map_err(|e| {
+                admin_error!("Failed to record session use {:?}", e);
+                e
+            })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) fn process_apitokenuse(&mut self, atu: &ApiTokenUse) -> Result<(), OperationError> {
+        let entry = self.qs_write.internal_search_uuid(atu.target_uuid)?;
+
+        // The token may have been revoked since it was used.
+        if !entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .map(|tokens| tokens.contains_key(&atu.token_id))
+            .unwrap_or(false)
+        {
+            debug!(token_id = %atu.token_id, "Api token no longer exists, not recording use");
+            return Ok(());
         }
+
+        let use_count = entry
+            .get_ava_as_credential_usage_map(Attribute::CredentialUsage)
+            .and_then(|usage| usage.get(&(atu.token_id, CredentialFactor::ApiToken)))
+            .map(|usage| usage.use_count)
+            .unwrap_or_default()
+            .saturating_add(1);
+
+        let modlist = ModifyList::new_list(vec![Modify::Present(
+            Attribute::CredentialUsage,
+            Value::CredentialUsage(
+                atu.token_id,
+                CredentialFactor::ApiToken,
+                CredentialUsage {
+                    last_used: atu.used_at,
+                    use_count,
+                    source: atu.source.clone(),
+                    user_agent: None,
+                },
+            ),
+        )]);
+
+        self.qs_write
+            .internal_modify_uuid(atu.target_uuid, &modlist)
+            .map_err(|e| {
+                admin_error!("Failed to record api token use {:?}", e);
+                e
+            })
     }
 
     #[instrument(level = "debug", skip_all)]
@@ -2142,9 +3452,41 @@ impl IdmServerProxyWriteTransaction<'_> {
         self.applications.commit();
         self.oauth2rs.commit();
         self.cred_update_sessions.commit();
+        self.account_recovery.commit();
+
+        let pending_activity = std::mem::take(&mut self.qs_write.pending_activity);
 
         trace!("cred_update_session.commit");
-        self.qs_write.commit()
+        self.qs_write.commit()?;
+
+        for event in pending_activity {
+            send_activity(&self.activity_tx, event);
+        }
+
+        for notification in self.pending_notifications {
+            send_notification(&self.notify_tx, notification);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn submit_audit_event(&self, event: AuditEvent) {
+        if self.audit_tx.send(event).is_err() {
+            error!("Unable to submit audit event to queue");
+        }
+    }
+
+    /// Activity events are only sent once the changes they describe are committed.
+    pub(crate) fn queue_activity_event(&mut self, event: AuditEvent) {
+        if self.activity_tx.is_some() {
+            self.qs_write.pending_activity.push(event);
+        }
+    }
+
+    pub(crate) fn queue_notification(&mut self, notification: SecurityNotification) {
+        if self.notify_tx.is_some() {
+            self.pending_notifications.push(notification);
+        }
     }
 
     #[instrument(level = "debug", skip_all)]
@@ -2204,10 +3546,12 @@ mod tests {
     use crate::idm::account::DestroySessionTokenEvent;
     use crate::idm::accountpolicy::ResolvedAccountPolicy;
     use crate::idm::audit::AuditEvent;
+    use crate::idm::authmetrics::AuthFunnelStep;
     use crate::idm::delayed::{AuthSessionRecord, DelayedAction};
     use crate::idm::event::{AuthEvent, AuthResult};
     use crate::idm::event::{
-        LdapAuthEvent, PasswordChangeEvent, RadiusAuthTokenEvent, RegenerateRadiusSecretEvent,
+        LdapAuthEvent, PasswordChangeEvent, RadiusAuthTokenEvent, RadiusCertificateIssueEvent,
+        ReadAccountPolicyEvent, RegenerateRadiusSecretEvent, SshCertificateIssueEvent,
         UnixGroupTokenEvent, UnixPasswordChangeEvent, UnixUserAuthEvent, UnixUserTokenEvent,
     };
 
@@ -2216,7 +3560,7 @@ mod tests {
     use crate::modify::{Modify, ModifyList};
     use crate::prelude::*;
     use crate::server::keys::KeyProvidersTransaction;
-    use crate::value::{AuthType, SessionState};
+    use crate::value::{AuthType, CredentialFactor, CredentialType, SessionState};
     use compact_jwt::{traits::JwsVerifiable, JwsCompact, JwsEs256Verifier, JwsVerifier};
     use kanidm_lib_crypto::CryptoPolicy;
 
@@ -2501,6 +3845,96 @@ mod tests {
         idms_delayed.check_is_empty_or_panic();
     }
 
+    #[idm_test]
+    async fn test_idm_auth_funnel_metrics(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+
+        check_testperson_password(idms, TEST_PASSWORD, ct).await;
+
+        let sid = init_authsession_sid(idms, ct, "testperson1").await;
+        let mut idms_auth = idms.auth().await.unwrap();
+        let r = idms_auth
+            .auth(
+                &AuthEvent::cred_step_password(sid, TEST_PASSWORD_INC),
+                ct,
+                Source::Internal.into(),
+            )
+            .await;
+        assert!(matches!(
+            r,
+            Ok(AuthResult {
+                state: AuthState::Denied(_),
+                ..
+            })
+        ));
+        idms_auth.commit().expect("Must not fail");
+
+        let metrics = idms.auth_funnel_metrics();
+        assert_eq!(metrics.initiated, 2);
+        assert_eq!(metrics.get(&AuthMech::Password, AuthFunnelStep::Chosen), 2);
+        assert_eq!(
+            metrics.get(&AuthMech::Password, AuthFunnelStep::Submitted),
+            2
+        );
+        assert_eq!(
+            metrics.get(&AuthMech::Password, AuthFunnelStep::Succeeded),
+            1
+        );
+        assert_eq!(metrics.get(&AuthMech::Password, AuthFunnelStep::Denied), 1);
+        assert_eq!(metrics.get(&AuthMech::Passkey, AuthFunnelStep::Chosen), 0);
+    }
+
+    #[idm_test]
+    async fn test_idm_credential_usage_recorded(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let cred_id = init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+
+        // Each authentication records the use of the password.
+        for _ in 0..2 {
+            check_testperson_password(idms, TEST_PASSWORD, ct).await;
+
+            let da = idms_delayed.try_recv().expect("invalid");
+            assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+            let r = idms.delayed_action(ct, da).await;
+            assert_eq!(Ok(true), r);
+        }
+        idms_delayed.check_is_empty_or_panic();
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let person = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_TESTPERSON_1)
+            .expect("failed");
+        let usage = person
+            .get_ava_as_credential_usage_map(Attribute::CredentialUsage)
+            .expect("Credential usage must be present!");
+        // The password, and each session that was created.
+        assert_eq!(usage.len(), 3);
+        assert_eq!(
+            usage
+                .keys()
+                .filter(|(_, factor)| *factor == CredentialFactor::Session)
+                .count(),
+            2
+        );
+
+        let password_usage = usage
+            .get(&(cred_id, CredentialFactor::Password))
+            .expect("Password usage is missing!");
+        assert_eq!(password_usage.use_count, 2);
+        assert_eq!(password_usage.last_used, OffsetDateTime::UNIX_EPOCH + ct);
+        // Internal authentication has no origin.
+        assert_eq!(password_usage.source, None);
+    }
+
     #[idm_test]
     async fn test_idm_simple_password_spn_auth(
         idms: &IdmServer,
@@ -2617,6 +4051,82 @@ mod tests {
         idms_auth.commit().expect("Must not fail");
     }
 
+    #[idm_test(audit = 1)]
+    async fn test_idm_audit_activity(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+        idms_audit: &mut IdmServerAudit,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+
+        // Until it is enabled, routine activity is not audited.
+        check_testperson_password(idms, TEST_PASSWORD, ct).await;
+        idms_audit.check_is_empty_or_panic();
+
+        idms.audit_activity_enable();
+        check_testperson_password(idms, TEST_PASSWORD, ct).await;
+
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::AuthenticationSucceeded { uuid, .. }) => {
+                assert_eq!(uuid, UUID_TESTPERSON_1);
+            }
+            _ => panic!("Oh no"),
+        }
+
+        // Changes made by users are audited once they are committed. Internal changes
+        // are not audited.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let idm_admin = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("failed");
+        let ident = Identity::from_impersonate_entry_readwrite(idm_admin);
+
+        assert!(idms_prox_write
+            .qs_write
+            .impersonate_modify(
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(UUID_TESTPERSON_1))),
+                &filter_all!(f_eq(Attribute::Uuid, PartialValue::Uuid(UUID_TESTPERSON_1))),
+                &ModifyList::new_purge_and_set(Attribute::DisplayName, Value::new_utf8s("Test")),
+                &ident,
+            )
+            .is_ok());
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_TESTPERSON_1,
+                &ModifyList::new_purge_and_set(Attribute::DisplayName, Value::new_utf8s("Person")),
+            )
+            .is_ok());
+
+        idms_audit.check_is_empty_or_panic();
+        assert!(idms_prox_write.commit().is_ok());
+
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::EntriesModified {
+                actor,
+                targets,
+                attrs,
+                ..
+            }) => {
+                assert_eq!(actor, UUID_IDM_ADMIN);
+                assert!(targets.contains(&UUID_TESTPERSON_1));
+                assert!(attrs.contains(&Attribute::DisplayName));
+            }
+            _ => panic!("Oh no"),
+        }
+
+        // Clear out the session records
+        for _ in 0..2 {
+            let da = idms_delayed.try_recv().expect("invalid");
+            assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+        }
+    }
+
     #[idm_test]
     async fn test_idm_simple_password_reset(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
         let pce = PasswordChangeEvent::new_internal(UUID_ADMIN, TEST_PASSWORD);
@@ -2691,6 +4201,154 @@ mod tests {
         assert_eq!(r1, tok_r.secret);
     }
 
+    #[idm_test]
+    async fn test_idm_radius_certificate(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
+        use kanidm_lib_crypto::mtls::get_group;
+        use openssl::ec::EcKey;
+        use openssl::hash::MessageDigest;
+        use openssl::pkey::PKey;
+        use openssl::x509::{X509Req, X509};
+
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![E_TESTPERSON_1.clone()])
+            .expect("unable to create test person");
+
+        let rrse = RegenerateRadiusSecretEvent::new_internal(UUID_TESTPERSON_1);
+        idms_prox_write
+            .regenerate_radius_secret(&rrse)
+            .expect("Failed to reset radius credential 1");
+
+        let client_key = EcKey::generate(&get_group().unwrap())
+            .and_then(PKey::from_ec_key)
+            .unwrap();
+        let mut csr = X509Req::builder().unwrap();
+        csr.set_pubkey(&client_key).unwrap();
+        csr.sign(&client_key, MessageDigest::sha256()).unwrap();
+        let csr = String::from_utf8(csr.build().to_pem().unwrap()).unwrap();
+
+        // Garbage is rejected.
+        let rcie = RadiusCertificateIssueEvent::new_internal(UUID_TESTPERSON_1, "csr".to_string());
+        assert_eq!(
+            idms_prox_write.issue_radius_certificate(&rcie),
+            Err(OperationError::InvalidRequestState)
+        );
+
+        let rcie = RadiusCertificateIssueEvent::new_internal(UUID_TESTPERSON_1, csr);
+        let cert_pem = idms_prox_write
+            .issue_radius_certificate(&rcie)
+            .expect("Failed to issue radius certificate");
+
+        // The certificate is signed by the radius certificate authority.
+        let ca_pem = idms_prox_write
+            .radius_ca_certificate_pem()
+            .expect("Failed to get radius certificate authority");
+        let ca = X509::from_pem(ca_pem.as_bytes()).unwrap();
+        let cert = X509::from_pem(cert_pem.as_bytes()).unwrap();
+        assert!(cert.verify(&ca.public_key().unwrap()).unwrap());
+
+        let serial = hex::encode(cert.serial_number().to_bn().unwrap().to_vec());
+
+        idms_prox_write.commit().expect("failed to commit");
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let person_entry = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_TESTPERSON_1)
+            .expect("Can't access admin entry.");
+
+        let rate = RadiusAuthTokenEvent::new_impersonate(person_entry, UUID_TESTPERSON_1);
+        let tok_r = idms_prox_read
+            .get_radiusauthtoken(&rate, duration_from_epoch_now())
+            .expect("Failed to generate radius auth token");
+
+        assert_eq!(tok_r.certificate_serials, vec![serial]);
+    }
+
+    #[idm_test]
+    async fn test_idm_ssh_certificate(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
+        use sshkey_attest::proto::PublicKey as SshPublicKey;
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let e_group: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("ssh_users")),
+            (Attribute::Member, Value::Refer(UUID_TESTPERSON_1))
+        );
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![E_TESTPERSON_1.clone(), e_group])
+            .expect("unable to create test entries");
+
+        let person_entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_TESTPERSON_1)
+            .expect("Can't access test person entry.");
+        let ident = Identity::from_impersonate_entry_readwrite(person_entry);
+
+        let public_key =
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAeGW1P6Pc2rPq0XqbRaDKBcXZUPRklo0L1EyR30CwoP laptop"
+                .to_string();
+
+        // Garbage is rejected.
+        let scie =
+            SshCertificateIssueEvent::from_parts(ident.clone(), "ssh-ed25519 invalid".to_string())
+                .expect("Failed to build event");
+        assert_eq!(
+            idms_prox_write.issue_ssh_certificate(&scie, ct),
+            Err(OperationError::VL0001ValueSshPublicKeyString)
+        );
+
+        // A read only session can't request a certificate.
+        let scie = SshCertificateIssueEvent::from_parts(
+            ident.project_with_scope(AccessScope::ReadOnly),
+            public_key.clone(),
+        )
+        .expect("Failed to build event");
+        assert_eq!(
+            idms_prox_write.issue_ssh_certificate(&scie, ct),
+            Err(OperationError::AccessDenied)
+        );
+
+        let scie =
+            SshCertificateIssueEvent::from_parts(ident, public_key).expect("Failed to build event");
+        let certificate = idms_prox_write
+            .issue_ssh_certificate(&scie, ct)
+            .expect("Failed to issue ssh certificate");
+
+        let cert =
+            sshkeys::Certificate::from_string(&certificate).expect("Invalid ssh certificate");
+        assert_eq!(cert.key_id, "testperson1@example.com");
+        assert_eq!(
+            cert.valid_principals,
+            vec!["testperson1".to_string(), "ssh_users".to_string()]
+        );
+        assert_eq!(
+            cert.valid_before - cert.valid_after,
+            SSH_USER_CERTIFICATE_VALIDITY + SSH_USER_CERTIFICATE_BACKDATE
+        );
+
+        // The certificate is signed by a published certificate authority.
+        let ca_public_keys = idms_prox_write
+            .ssh_ca_public_keys()
+            .expect("Failed to get ssh certificate authorities");
+        assert_eq!(ca_public_keys.len(), 1);
+        let ca_public_key =
+            SshPublicKey::from_string(&ca_public_keys[0]).expect("Invalid ssh ca public key");
+        assert_eq!(
+            cert.signature_key.fingerprint().hash,
+            ca_public_key.fingerprint().hash
+        );
+
+        idms_prox_write.commit().expect("failed to commit");
+    }
+
     #[idm_test]
     async fn test_idm_unixusertoken(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
         let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
@@ -2771,30 +4429,420 @@ mod tests {
     }
 
     #[idm_test]
-    async fn test_idm_simple_unix_password_reset(
+    async fn test_idm_unixusertoken_account_policy(
         idms: &IdmServer,
         _idms_delayed: &IdmServerDelayed,
     ) {
         let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
-        // make the admin a valid posix account
         let me_posix = ModifyEvent::new_internal_invalid(
             filter!(f_eq(Attribute::Name, PartialValue::new_iname("admin"))),
             ModifyList::new_list(vec![
                 Modify::Present(Attribute::Class, EntryClass::PosixAccount.into()),
                 Modify::Present(Attribute::GidNumber, Value::new_uint32(2001)),
+                Modify::Present(Attribute::PosixHomeQuota, Value::new_uint32(1024)),
             ]),
         );
         assert!(idms_prox_write.qs_write.modify(&me_posix).is_ok());
 
-        let pce = UnixPasswordChangeEvent::new_internal(UUID_ADMIN, TEST_PASSWORD);
+        let e: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Class, EntryClass::AccountPolicy.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Member, Value::Refer(UUID_ADMIN)),
+            (Attribute::PosixDefaultShell, Value::new_utf8s("/bin/zsh")),
+            (
+                Attribute::PosixHomeTemplate,
+                Value::new_utf8s("{name}-{gidnumber}")
+            ),
+            (
+                Attribute::PosixGecosFormat,
+                Value::new_utf8s("{name},{spn},{other}")
+            ),
+            (Attribute::PosixHomeSkeleton, Value::new_utf8s("developers"))
+        );
 
-        assert!(idms_prox_write.set_unix_account_password(&pce).is_ok());
-        assert!(idms_prox_write.commit().is_ok());
+        let ce = CreateEvent::new_internal(vec![e]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        idms_prox_write.commit().expect("failed to commit");
 
-        let mut idms_auth = idms.auth().await.unwrap();
-        // Check auth verification of the password
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let uute = UnixUserTokenEvent::new_internal(UUID_ADMIN);
+        let tok_r = idms_prox_read
+            .get_unixusertoken(&uute, duration_from_epoch_now())
+            .expect("Failed to generate unix user token");
 
-        let uuae_good = UnixUserAuthEvent::new_internal(UUID_ADMIN, TEST_PASSWORD);
+        assert_eq!(tok_r.shell.as_deref(), Some("/bin/zsh"));
+        assert_eq!(tok_r.home_directory.as_deref(), Some("admin-2001"));
+        assert_eq!(tok_r.home_skeleton.as_deref(), Some("developers"));
+        assert_eq!(tok_r.home_quota, Some(1024));
+        assert_eq!(
+            tok_r.gecos.as_deref(),
+            Some("admin,admin@example.com,{other}")
+        );
+        drop(idms_prox_read);
+
+        // The shell of the account takes precedence over the account policy.
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        let me_shell = ModifyEvent::new_internal_invalid(
+            filter!(f_eq(Attribute::Name, PartialValue::new_iname("admin"))),
+            ModifyList::new_purge_and_set(Attribute::LoginShell, Value::new_iutf8("/bin/bash")),
+        );
+        assert!(idms_prox_write.qs_write.modify(&me_shell).is_ok());
+        idms_prox_write.commit().expect("failed to commit");
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let tok_r = idms_prox_read
+            .get_unixusertoken(&uute, duration_from_epoch_now())
+            .expect("Failed to generate unix user token");
+
+        assert_eq!(tok_r.shell.as_deref(), Some("/bin/bash"));
+    }
+
+    #[idm_test]
+    async fn test_idm_unixusertoken_hbac(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        let me_posix = ModifyEvent::new_internal_invalid(
+            filter!(f_eq(Attribute::Name, PartialValue::new_iname("admin"))),
+            ModifyList::new_list(vec![
+                Modify::Present(Attribute::Class, EntryClass::PosixAccount.into()),
+                Modify::Present(Attribute::GidNumber, Value::new_uint32(2001)),
+            ]),
+        );
+        assert!(idms_prox_write.qs_write.modify(&me_posix).is_ok());
+        idms_prox_write.commit().expect("failed to commit");
+
+        // With no rules in the domain, hosts decide access locally.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let uute = UnixUserTokenEvent::new_internal(UUID_ADMIN);
+        let tok_r = idms_prox_read
+            .get_unixusertoken(&uute, duration_from_epoch_now())
+            .expect("Failed to generate unix user token");
+        assert!(tok_r.hbac.is_none());
+        drop(idms_prox_read);
+
+        let group_uuid = Uuid::new_v4();
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        let e_group: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Uuid, Value::Uuid(group_uuid)),
+            (Attribute::Member, Value::Refer(UUID_ADMIN))
+        );
+        let e_allow: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::HbacRule.to_value()),
+            (Attribute::Name, Value::new_iname("allow_servers")),
+            (Attribute::HbacMember, Value::Refer(group_uuid)),
+            (Attribute::HbacHost, Value::new_iutf8("db1")),
+            (Attribute::HbacHost, Value::new_iutf8("db2"))
+        );
+        let e_deny: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::HbacRule.to_value()),
+            (Attribute::Name, Value::new_iname("deny_ssh")),
+            (Attribute::HbacMember, Value::Refer(UUID_ADMIN)),
+            (Attribute::HbacHost, Value::new_iutf8("db2")),
+            (Attribute::HbacService, Value::new_iutf8("sshd")),
+            (Attribute::HbacDeny, Value::new_bool(true))
+        );
+        // A rule for someone else does not apply.
+        let e_other: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::HbacRule.to_value()),
+            (Attribute::Name, Value::new_iname("allow_other")),
+            (Attribute::HbacMember, Value::Refer(UUID_IDM_ADMIN))
+        );
+
+        let ce = CreateEvent::new_internal(vec![e_group, e_allow, e_deny, e_other]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        idms_prox_write.commit().expect("failed to commit");
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let tok_r = idms_prox_read
+            .get_unixusertoken(&uute, duration_from_epoch_now())
+            .expect("Failed to generate unix user token");
+
+        let hbac = tok_r.hbac.expect("No hbac policy in unix user token");
+        assert_eq!(hbac.allow.len(), 1);
+        assert_eq!(hbac.allow[0].name, "allow_servers");
+        assert_eq!(hbac.deny.len(), 1);
+        assert_eq!(hbac.deny[0].name, "deny_ssh");
+
+        assert!(hbac.is_allowed("db1", Some("sshd")));
+        assert!(hbac.is_allowed("db2", Some("login")));
+        assert!(!hbac.is_allowed("db2", Some("sshd")));
+        assert!(!hbac.is_allowed("web1", Some("sshd")));
+    }
+
+    #[idm_test]
+    async fn test_idm_unixusertoken_hbac_host_group(
+        idms: &IdmServer,
+        _idms_delayed: &IdmServerDelayed,
+    ) {
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        let me_posix = ModifyEvent::new_internal_invalid(
+            filter!(f_eq(Attribute::Name, PartialValue::new_iname("admin"))),
+            ModifyList::new_list(vec![
+                Modify::Present(Attribute::Class, EntryClass::PosixAccount.into()),
+                Modify::Present(Attribute::GidNumber, Value::new_uint32(2001)),
+            ]),
+        );
+        assert!(idms_prox_write.qs_write.modify(&me_posix).is_ok());
+
+        let web1_uuid = Uuid::new_v4();
+        let host_group_uuid = Uuid::new_v4();
+        let empty_group_uuid = Uuid::new_v4();
+
+        let e_web1: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("web1")),
+            (Attribute::Uuid, Value::Uuid(web1_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("web1"))
+        );
+        let e_web2: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("web2")),
+            (Attribute::DisplayName, Value::new_utf8s("web2")),
+            (Attribute::HostTag, Value::new_iutf8("web-fleet"))
+        );
+        let e_db1: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("db1")),
+            (Attribute::DisplayName, Value::new_utf8s("db1")),
+            (Attribute::HostTag, Value::new_iutf8("prod-db"))
+        );
+        // Members are selected both directly and by tag.
+        let e_host_group: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::HostGroup.to_value()),
+            (Attribute::Name, Value::new_iname("web_servers")),
+            (Attribute::Uuid, Value::Uuid(host_group_uuid)),
+            (Attribute::HostGroupMember, Value::Refer(web1_uuid)),
+            (Attribute::HostTag, Value::new_iutf8("web-fleet"))
+        );
+        let e_empty_group: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::HostGroup.to_value()),
+            (Attribute::Name, Value::new_iname("empty_servers")),
+            (Attribute::Uuid, Value::Uuid(empty_group_uuid)),
+            (Attribute::HostTag, Value::new_iutf8("nothing-tagged"))
+        );
+        let e_allow: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::HbacRule.to_value()),
+            (Attribute::Name, Value::new_iname("allow_web")),
+            (Attribute::HbacMember, Value::Refer(UUID_ADMIN)),
+            (Attribute::HbacHostGroup, Value::Refer(host_group_uuid))
+        );
+        // A rule that targets only an empty host group must not apply to all hosts.
+        let e_allow_empty: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::HbacRule.to_value()),
+            (Attribute::Name, Value::new_iname("allow_empty")),
+            (Attribute::HbacMember, Value::Refer(UUID_ADMIN)),
+            (Attribute::HbacHostGroup, Value::Refer(empty_group_uuid))
+        );
+
+        let ce = CreateEvent::new_internal(vec![
+            e_web1,
+            e_web2,
+            e_db1,
+            e_host_group,
+            e_empty_group,
+            e_allow,
+            e_allow_empty,
+        ]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        idms_prox_write.commit().expect("failed to commit");
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let uute = UnixUserTokenEvent::new_internal(UUID_ADMIN);
+        let tok_r = idms_prox_read
+            .get_unixusertoken(&uute, duration_from_epoch_now())
+            .expect("Failed to generate unix user token");
+
+        let hbac = tok_r.hbac.expect("No hbac policy in unix user token");
+        assert_eq!(hbac.allow.len(), 1);
+        assert_eq!(hbac.allow[0].name, "allow_web");
+
+        assert!(hbac.is_allowed("web1", Some("sshd")));
+        assert!(hbac.is_allowed("web2", Some("sshd")));
+        assert!(!hbac.is_allowed("db1", Some("sshd")));
+    }
+
+    #[idm_test]
+    async fn test_idm_host_enrollment(idms: &IdmServer, idms_delayed: &mut IdmServerDelayed) {
+        use crate::idm::host::{
+            GenerateHostJoinTokenEvent, HostEnrollEvent, HostRotateCredentialEvent,
+        };
+
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let host_uuid = Uuid::new_v4();
+        let e_host: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Class, EntryClass::Host.to_value()),
+            (Attribute::Name, Value::new_iname("web1")),
+            (Attribute::Uuid, Value::Uuid(host_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("web1"))
+        );
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e_host])
+            .expect("unable to create host");
+
+        // Join tokens are limited in how long they are valid for.
+        let gjte = GenerateHostJoinTokenEvent {
+            ident: Identity::from_internal(),
+            target: host_uuid,
+            ttl: Some(Duration::from_secs(HOST_JOIN_TOKEN_MAX_TTL + 1)),
+        };
+        assert_eq!(
+            idms_prox_write.host_generate_join_token(&gjte, ct),
+            Err(OperationError::InvalidRequestState)
+        );
+
+        // Join tokens can't be issued to accounts that aren't hosts.
+        let gjte = GenerateHostJoinTokenEvent {
+            ident: Identity::from_internal(),
+            target: UUID_ADMIN,
+            ttl: None,
+        };
+        assert!(idms_prox_write.host_generate_join_token(&gjte, ct).is_err());
+
+        let gjte = GenerateHostJoinTokenEvent {
+            ident: Identity::from_internal(),
+            target: host_uuid,
+            ttl: None,
+        };
+        let join_token = idms_prox_write
+            .host_generate_join_token(&gjte, ct)
+            .expect("Failed to generate join token");
+
+        // An invalid join token is rejected.
+        let hee = HostEnrollEvent {
+            name: "web1".to_string(),
+            join_token: "invalid".to_string(),
+        };
+        assert_eq!(
+            idms_prox_write.host_enroll(&hee, ct),
+            Err(OperationError::AccessDenied)
+        );
+
+        // As is an expired one.
+        let hee = HostEnrollEvent {
+            name: "web1".to_string(),
+            join_token: join_token.clone(),
+        };
+        let expired = ct + Duration::from_secs(HOST_JOIN_TOKEN_DEFAULT_TTL + 1);
+        assert_eq!(
+            idms_prox_write.host_enroll(&hee, expired),
+            Err(OperationError::AccessDenied)
+        );
+
+        let credential = idms_prox_write
+            .host_enroll(&hee, ct)
+            .expect("Failed to enroll host");
+
+        // The join token can only be used once.
+        assert_eq!(
+            idms_prox_write.host_enroll(&hee, ct),
+            Err(OperationError::AccessDenied)
+        );
+
+        // The host can authenticate with its credential.
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(credential.into(), ct)
+            .expect("Unable to authenticate with host credential");
+        assert_eq!(ident.get_uuid(), Some(host_uuid));
+        assert_eq!(ident.access_scope(), AccessScope::ReadOnly);
+
+        let hrce = HostRotateCredentialEvent {
+            ident: ident.clone(),
+        };
+        let rotated = idms_prox_write
+            .host_rotate_credential(&hrce, ct)
+            .expect("Failed to rotate host credential");
+
+        // The previous credential is revoked, so it can't be rotated again.
+        assert_eq!(
+            idms_prox_write.host_rotate_credential(&hrce, ct),
+            Err(OperationError::AccessDenied)
+        );
+
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(rotated.into(), ct)
+            .expect("Unable to authenticate with rotated host credential");
+        assert_eq!(ident.get_uuid(), Some(host_uuid));
+
+        let host_entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(host_uuid)
+            .expect("Can't access host entry.");
+        let api_tokens = host_entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .expect("No host credential present");
+        assert_eq!(api_tokens.len(), 1);
+        assert!(api_tokens.contains_key(&ident.get_session_id()));
+
+        // Only hosts may rotate a host credential.
+        let admin_entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_ADMIN)
+            .expect("Can't access admin entry.");
+        let hrce = HostRotateCredentialEvent {
+            ident: Identity::from_impersonate_entry_readwrite(admin_entry),
+        };
+        assert_eq!(
+            idms_prox_write.host_rotate_credential(&hrce, ct),
+            Err(OperationError::AccessDenied)
+        );
+
+        idms_prox_write.commit().expect("failed to commit");
+
+        // Each use of the api token is recorded.
+        while let Ok(da) = idms_delayed.try_recv() {
+            assert!(matches!(da, DelayedAction::ApiTokenUse(_)));
+        }
+    }
+
+    #[idm_test]
+    async fn test_idm_simple_unix_password_reset(
+        idms: &IdmServer,
+        _idms_delayed: &IdmServerDelayed,
+    ) {
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        // make the admin a valid posix account
+        let me_posix = ModifyEvent::new_internal_invalid(
+            filter!(f_eq(Attribute::Name, PartialValue::new_iname("admin"))),
+            ModifyList::new_list(vec![
+                Modify::Present(Attribute::Class, EntryClass::PosixAccount.into()),
+                Modify::Present(Attribute::GidNumber, Value::new_uint32(2001)),
+            ]),
+        );
+        assert!(idms_prox_write.qs_write.modify(&me_posix).is_ok());
+
+        let pce = UnixPasswordChangeEvent::new_internal(UUID_ADMIN, TEST_PASSWORD);
+
+        assert!(idms_prox_write.set_unix_account_password(&pce).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_auth = idms.auth().await.unwrap();
+        // Check auth verification of the password
+
+        let uuae_good = UnixUserAuthEvent::new_internal(UUID_ADMIN, TEST_PASSWORD);
         let a1 = idms_auth
             .auth_unix(&uuae_good, Duration::from_secs(TEST_CURRENT_TIME))
             .await;
@@ -3435,6 +5483,131 @@ mod tests {
         idms_auth.commit().expect("Must not fail");
     }
 
+    #[idm_test(audit = 1)]
+    async fn test_idm_account_lockout_policy(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+        idms_audit: &mut IdmServerAudit,
+    ) {
+        async fn password_attempt(idms: &IdmServer, pw: &str, ct: Duration) -> AuthState {
+            let sid = init_authsession_sid(idms, ct, "testperson1").await;
+            let mut idms_auth = idms.auth().await.unwrap();
+            let anon_step = AuthEvent::cred_step_password(sid, pw);
+            let AuthResult { state, .. } = idms_auth
+                .auth(&anon_step, ct, Source::Internal.into())
+                .await
+                .expect("Failed to process auth step");
+            idms_auth.commit().expect("Must not fail");
+            state
+        }
+
+        const LOCKED_OUT: &str = "Account is locked due to repeated authentication failures";
+
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+
+        // Lock credentials for 10 minutes after two failures.
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_ALL_ACCOUNTS,
+                &ModifyList::new_list(vec![
+                    Modify::Present(Attribute::AuthLockoutThreshold, Value::new_uint32(2)),
+                    Modify::Present(Attribute::AuthLockoutDuration, Value::new_uint32(600)),
+                ]),
+            )
+            .expect("Failed to set lockout policy");
+        idms_prox_write.commit().expect("Must not fail");
+
+        // The first failure only delays the next attempt.
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        match password_attempt(idms, TEST_PASSWORD_INC, ct).await {
+            AuthState::Denied(reason) => assert!(!reason.starts_with(LOCKED_OUT)),
+            _ => panic!("Auth was not denied"),
+        };
+        assert!(matches!(
+            idms_audit.audit_rx().try_recv(),
+            Ok(AuditEvent::AuthenticationDenied { .. })
+        ));
+
+        // Reaching the threshold locks the credential, and the user is told so.
+        let ct = ct + Duration::from_secs(2);
+        match password_attempt(idms, TEST_PASSWORD_INC, ct).await {
+            AuthState::Denied(reason) => assert!(reason.starts_with(LOCKED_OUT)),
+            _ => panic!("Auth was not denied"),
+        };
+        assert!(matches!(
+            idms_audit.audit_rx().try_recv(),
+            Ok(AuditEvent::AuthenticationDenied { .. })
+        ));
+
+        // No further attempts are allowed while locked.
+        let ct = ct + Duration::from_secs(300);
+        let mut idms_auth = idms.auth().await.unwrap();
+        let AuthResult { sessionid, .. } = idms_auth
+            .auth(
+                &AuthEvent::named_init("testperson1"),
+                ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to init auth session");
+        let AuthResult { state, .. } = idms_auth
+            .auth(
+                &AuthEvent::begin_mech(sessionid, AuthMech::Password),
+                ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to begin auth session");
+        match state {
+            AuthState::Denied(reason) => assert!(reason.starts_with(LOCKED_OUT)),
+            _ => panic!("Auth was not denied"),
+        };
+
+        // An administrator can view and clear the lock, but only with a read-write session.
+        let idm_admin = idms_auth
+            .qs_read
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("failed");
+
+        let ident = Identity::from_impersonate_entry_readonly(idm_admin.clone());
+        assert_eq!(
+            idms_auth
+                .credential_softlock_clear(&ident, UUID_TESTPERSON_1)
+                .await,
+            Err(OperationError::AccessDenied)
+        );
+
+        let ident = Identity::from_impersonate_entry_readwrite(idm_admin);
+        let status = idms_auth
+            .credential_softlock_status(&ident, UUID_TESTPERSON_1, ct)
+            .await
+            .expect("Failed to get softlock status");
+        assert_eq!(status.failures, 2);
+        assert!(status.locked_out);
+        assert_eq!(
+            status.unlock_at,
+            Some(time::OffsetDateTime::UNIX_EPOCH + Duration::from_secs(TEST_CURRENT_TIME + 602))
+        );
+
+        assert!(idms_auth
+            .credential_softlock_clear(&ident, UUID_TESTPERSON_1)
+            .await
+            .is_ok());
+        idms_auth.commit().expect("Must not fail");
+
+        match password_attempt(idms, TEST_PASSWORD, ct).await {
+            AuthState::Success(..) => {}
+            _ => panic!("Auth was not successful"),
+        };
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+    }
+
     #[idm_test]
     async fn test_idm_account_unix_softlocking(
         idms: &IdmServer,
@@ -3524,21 +5697,195 @@ mod tests {
     }
 
     #[idm_test]
-    async fn test_idm_expired_auth_session_cleanup(
-        idms: &IdmServer,
-        _idms_delayed: &mut IdmServerDelayed,
-    ) {
+    async fn test_idm_session_idle_timeout(idms: &IdmServer, idms_delayed: &mut IdmServerDelayed) {
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
-        let expiry_a = ct + Duration::from_secs((DEFAULT_AUTH_SESSION_EXPIRY + 1).into());
-        let expiry_b = ct + Duration::from_secs(((DEFAULT_AUTH_SESSION_EXPIRY + 1) * 2).into());
-
-        let session_a = Uuid::new_v4();
-        let session_b = Uuid::new_v4();
-
-        // We need to put the credential on the admin.
-        let cred_id = init_testperson_w_password(idms, TEST_PASSWORD)
-            .await
-            .expect("Failed to setup admin account");
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let modlist = ModifyList::new_purge_and_set(
+            Attribute::AuthSessionIdleTimeout,
+            Value::Uint32(MINIMUM_AUTH_SESSION_IDLE_TIMEOUT),
+        );
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(UUID_IDM_ALL_ACCOUNTS, &modlist)
+            .expect("Unable to set idle timeout");
+        assert!(idms_prox_write.commit().is_ok());
+
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+        let token = check_testperson_password(idms, TEST_PASSWORD, ct).await;
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+        let r = idms.delayed_action(ct, da).await;
+        assert_eq!(Ok(true), r);
+        idms_delayed.check_is_empty_or_panic();
+
+        let idle_timeout = Duration::from_secs(MINIMUM_AUTH_SESSION_IDLE_TIMEOUT.into());
+
+        // Using the session records the use, which refreshes it.
+        let used_at = ct + idle_timeout / 2;
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let uat = idms_prox_read
+            .validate_client_auth_info_to_uat(token.clone().into(), used_at)
+            .expect("Failed to validate");
+        assert_eq!(uat.idle_timeout, Some(MINIMUM_AUTH_SESSION_IDLE_TIMEOUT));
+        idms_prox_read
+            .validate_client_auth_info_to_ident(token.clone().into(), used_at)
+            .expect("Failed to validate");
+        drop(idms_prox_read);
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::SessionUse(_)));
+        let r = idms.delayed_action(used_at, da).await;
+        assert_eq!(Ok(true), r);
+
+        // Still valid after the idle timeout has passed since the session began.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(token.clone().into(), ct + idle_timeout)
+            .expect("Failed to validate");
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::SessionUse(_)));
+
+        // But not once it has been idle for the timeout.
+        assert_eq!(
+            idms_prox_read
+                .validate_client_auth_info_to_ident(token.into(), used_at + idle_timeout)
+                .map(|_| ()),
+            Err(OperationError::SessionExpired)
+        );
+    }
+
+    #[idm_test]
+    async fn test_idm_session_binding(idms: &IdmServer, idms_delayed: &mut IdmServerDelayed) {
+        use crate::idm::session_binding::test_session_proof;
+        use compact_jwt::JwsEs256Signer;
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+
+        let signer = JwsEs256Signer::generate_es256()
+            .expect("Unable to create signer")
+            .set_sign_option_embed_jwk(true);
+        let (proof, jkt) = test_session_proof(&signer, ct, None);
+
+        // The browser presents its proof when the session begins.
+        let mut client_auth_info: ClientAuthInfo = Source::Internal.into();
+        client_auth_info.session_proof = Some(proof.clone());
+
+        let mut idms_auth = idms.auth().await.unwrap();
+        let AuthResult { sessionid, .. } = idms_auth
+            .auth(
+                &AuthEvent::named_init("testperson1"),
+                ct,
+                client_auth_info.clone(),
+            )
+            .await
+            .expect("Failed to begin auth");
+        idms_auth
+            .auth(
+                &AuthEvent::begin_mech(sessionid, AuthMech::Password),
+                ct,
+                client_auth_info.clone(),
+            )
+            .await
+            .expect("Failed to choose mech");
+        let token = match idms_auth
+            .auth(
+                &AuthEvent::cred_step_password(sessionid, TEST_PASSWORD),
+                ct,
+                client_auth_info,
+            )
+            .await
+        {
+            Ok(AuthResult {
+                state: AuthState::Success(token, AuthIssueSession::Token),
+                ..
+            }) => *token,
+            _ => panic!("Failed to authenticate"),
+        };
+        idms_auth.commit().expect("Must not fail");
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+        let r = idms.delayed_action(ct, da).await;
+        assert_eq!(Ok(true), r);
+        idms_delayed.check_is_empty_or_panic();
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        // The token alone is refused.
+        assert_eq!(
+            idms_prox_read
+                .validate_client_auth_info_to_ident(token.clone().into(), ct)
+                .map(|_| ()),
+            Err(OperationError::NotAuthenticated)
+        );
+
+        // With a proof from the browser's key it's accepted.
+        let mut client_auth_info: ClientAuthInfo = token.clone().into();
+        client_auth_info.session_proof = Some(proof);
+        let uat = idms_prox_read
+            .validate_client_auth_info_to_uat(client_auth_info.clone(), ct)
+            .expect("Failed to validate");
+        assert_eq!(uat.session_key, Some(jkt));
+        idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .expect("Failed to validate");
+
+        // Once the browser knows its session, later proofs must be for that session.
+        let later = ct + Duration::from_secs(60);
+        let mut client_auth_info: ClientAuthInfo = token.clone().into();
+        client_auth_info.session_proof =
+            Some(test_session_proof(&signer, later, Some(uat.session_id)).0);
+        idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, later)
+            .expect("Failed to validate");
+
+        let mut client_auth_info: ClientAuthInfo = token.clone().into();
+        client_auth_info.session_proof = Some(test_session_proof(&signer, later, None).0);
+        assert_eq!(
+            idms_prox_read
+                .validate_client_auth_info_to_ident(client_auth_info, later)
+                .map(|_| ()),
+            Err(OperationError::NotAuthenticated)
+        );
+
+        // A proof from another key is refused.
+        let other_signer = JwsEs256Signer::generate_es256()
+            .expect("Unable to create signer")
+            .set_sign_option_embed_jwk(true);
+        let (other_proof, _) = test_session_proof(&other_signer, ct, None);
+        let mut client_auth_info: ClientAuthInfo = token.into();
+        client_auth_info.session_proof = Some(other_proof);
+        assert_eq!(
+            idms_prox_read
+                .validate_client_auth_info_to_ident(client_auth_info, ct)
+                .map(|_| ()),
+            Err(OperationError::NotAuthenticated)
+        );
+    }
+
+    #[idm_test]
+    async fn test_idm_expired_auth_session_cleanup(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let expiry_a = ct + Duration::from_secs((DEFAULT_AUTH_SESSION_EXPIRY + 1).into());
+        let expiry_b = ct + Duration::from_secs(((DEFAULT_AUTH_SESSION_EXPIRY + 1) * 2).into());
+
+        let session_a = Uuid::new_v4();
+        let session_b = Uuid::new_v4();
+
+        // We need to put the credential on the admin.
+        let cred_id = init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
 
         // Assert no sessions present
         let mut idms_prox_read = idms.proxy_read().await.unwrap();
@@ -3560,6 +5907,9 @@ mod tests {
             issued_by: IdentityId::User(UUID_ADMIN),
             scope: SessionScope::ReadOnly,
             type_: AuthType::Passkey,
+            factors: Vec::new(),
+            source: None,
+            user_agent: None,
         });
         // Persist it.
         let r = idms.delayed_action(ct, da).await;
@@ -3592,6 +5942,9 @@ mod tests {
             issued_by: IdentityId::User(UUID_ADMIN),
             scope: SessionScope::ReadOnly,
             type_: AuthType::Passkey,
+            factors: Vec::new(),
+            source: None,
+            user_agent: None,
         });
         // Persist it.
         let r = idms.delayed_action(expiry_a, da).await;
@@ -4273,4 +6626,592 @@ mod tests {
     ) {
         idm_fallback_auth_fixture(idms, _idms_delayed, true, Some(false), Some(())).await;
     }
+
+    #[idm_test]
+    async fn test_idm_unix_password_policy_deny(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let target_uuid = Uuid::new_v4();
+        let p = CryptoPolicy::minimum();
+
+        {
+            let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+            idms_prox_write
+                .qs_write
+                .internal_modify_uuid(
+                    UUID_IDM_ALL_ACCOUNTS,
+                    &ModifyList::new_purge_and_set(
+                        Attribute::AllowUnixPassword,
+                        Value::new_bool(false),
+                    ),
+                )
+                .expect("Unable to deny unix passwords");
+
+            let e = entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Class, EntryClass::PosixAccount.to_value()),
+                (Attribute::Uuid, Value::Uuid(target_uuid)),
+                (Attribute::Name, Value::new_iname("kevin")),
+                (Attribute::DisplayName, Value::new_utf8s("Kevin")),
+                (
+                    Attribute::UnixPassword,
+                    Value::Cred(
+                        "unix".to_string(),
+                        Credential::new_password_only(&p, "kampai").unwrap()
+                    )
+                )
+            );
+
+            assert!(idms_prox_write.qs_write.internal_create(vec![e]).is_ok());
+
+            // A new unix password can't be set.
+            let pce = UnixPasswordChangeEvent::new_internal(target_uuid, TEST_PASSWORD);
+            assert_eq!(
+                idms_prox_write.set_unix_account_password(&pce),
+                Err(OperationError::AccessDenied)
+            );
+
+            idms_prox_write.commit().expect("Must not fail");
+        }
+
+        // And the existing unix password can't be used.
+        let result = idms
+            .auth()
+            .await
+            .unwrap()
+            .auth_ldap(
+                &LdapAuthEvent {
+                    target: target_uuid,
+                    cleartext: "kampai".to_string(),
+                },
+                ct,
+            )
+            .await;
+
+        assert!(matches!(result, Ok(None)));
+    }
+
+    #[idm_test]
+    async fn test_idm_unix_login_host_tag_deny(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+        let me_posix = ModifyEvent::new_internal_invalid(
+            filter!(f_eq(Attribute::Name, PartialValue::new_iname("admin"))),
+            ModifyList::new_list(vec![
+                Modify::Present(Attribute::Class, EntryClass::PosixAccount.into()),
+                Modify::Present(Attribute::GidNumber, Value::new_uint32(2001)),
+            ]),
+        );
+        assert!(idms_prox_write.qs_write.modify(&me_posix).is_ok());
+
+        let pce = UnixPasswordChangeEvent::new_internal(UUID_ADMIN, TEST_PASSWORD);
+        assert!(idms_prox_write.set_unix_account_password(&pce).is_ok());
+
+        let e: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Class, EntryClass::AccountPolicy.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Member, Value::Refer(UUID_ADMIN)),
+            (Attribute::LoginHostTag, Value::new_iutf8("db-fleet"))
+        );
+
+        let ce = CreateEvent::new_internal(vec![e]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        // The password is correct, but the request is not from a host with the tag.
+        let mut idms_auth = idms.auth().await.unwrap();
+        let uuae_good = UnixUserAuthEvent::new_internal(UUID_ADMIN, TEST_PASSWORD);
+        let a1 = idms_auth
+            .auth_unix(&uuae_good, Duration::from_secs(TEST_CURRENT_TIME))
+            .await;
+        assert!(matches!(a1, Ok(None)));
+        assert!(idms_auth.commit().is_ok());
+
+        // No hosts have the tag, so the token permits no hosts.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let uute = UnixUserTokenEvent::new_internal(UUID_ADMIN);
+        let tok_r = idms_prox_read
+            .get_unixusertoken(&uute, duration_from_epoch_now())
+            .expect("Failed to generate unix user token");
+        assert_eq!(tok_r.login_hosts, Some(Vec::new()));
+    }
+
+    #[idm_test]
+    async fn test_idm_account_policy_read(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
+        let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
+
+        let e: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Class, EntryClass::AccountPolicy.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Member, Value::Refer(UUID_ADMIN)),
+            (Attribute::AuthPasswordMinimumLength, Value::Uint32(20)),
+            (
+                Attribute::CredentialTypeMinimum,
+                CredentialType::Passkey.into()
+            ),
+            (Attribute::LoginHostTag, Value::new_iutf8("db-fleet"))
+        );
+
+        let ce = CreateEvent::new_internal(vec![e]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let rape = ReadAccountPolicyEvent::new_internal(UUID_ADMIN);
+        let policy = idms_prox_read
+            .get_account_policy(&rape)
+            .expect("Failed to read account policy");
+
+        assert_eq!(policy.password_minimum_length, 20);
+        assert_eq!(policy.credential_type_minimum, "passkey");
+        assert_eq!(policy.login_host_tags, Some(vec!["db-fleet".to_string()]));
+    }
+
+    #[idm_test]
+    async fn test_idm_email_link_auth(idms: &IdmServer, idms_delayed: &mut IdmServerDelayed) {
+        let ct = duration_from_epoch_now();
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup test account");
+
+        let mut email_link_rx = idms
+            .email_link_subscribe()
+            .expect("Failed to subscribe to email links");
+
+        // Without a policy allowing it, email links are not offered.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let me_mail = ModifyEvent::new_internal_invalid(
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(UUID_TESTPERSON_1))),
+            ModifyList::new_purge_and_set(
+                Attribute::Mail,
+                Value::new_email_address_primary_s("testperson1@example.com")
+                    .expect("Invalid mail address"),
+            ),
+        );
+        assert!(idms_prox_write.qs_write.modify(&me_mail).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_auth = idms.auth().await.unwrap();
+        let AuthResult { state, .. } = idms_auth
+            .auth(
+                &AuthEvent::named_init("testperson1"),
+                ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to init auth session");
+        let AuthState::Choose(mechs) = state else {
+            panic!("Unexpected auth state");
+        };
+        assert!(!mechs.contains(&AuthMech::EmailLink));
+        drop(idms_auth);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let e: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Class, EntryClass::AccountPolicy.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Member, Value::Refer(UUID_TESTPERSON_1)),
+            (Attribute::AllowEmailLink, Value::Bool(true))
+        );
+        let ce = CreateEvent::new_internal(vec![e]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_auth = idms.auth().await.unwrap();
+        let AuthResult { sessionid, state } = idms_auth
+            .auth(
+                &AuthEvent::named_init("testperson1"),
+                ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to init auth session");
+        let AuthState::Choose(mechs) = state else {
+            panic!("Unexpected auth state");
+        };
+        assert!(mechs.contains(&AuthMech::EmailLink));
+
+        let AuthResult { sessionid, state } = idms_auth
+            .auth(
+                &AuthEvent::begin_mech(sessionid, AuthMech::EmailLink),
+                ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to begin email link");
+        assert!(
+            matches!(state, AuthState::Continue(allowed) if allowed == vec![AuthAllowed::EmailLink])
+        );
+        idms_auth.commit().expect("Must not fail");
+
+        let message = email_link_rx.try_recv().expect("No email link was sent");
+        assert_eq!(message.mail, "testperson1@example.com");
+
+        let mut idms_auth = idms.auth().await.unwrap();
+        let AuthResult { state, .. } = idms_auth
+            .auth(
+                &AuthEvent::cred_step_email_link(sessionid, &message.token),
+                ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to step email link");
+        assert!(matches!(
+            state,
+            AuthState::Success(_, AuthIssueSession::Token)
+        ));
+        idms_auth.commit().expect("Must not fail");
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+        idms_delayed.check_is_empty_or_panic();
+    }
+
+    #[idm_test]
+    async fn test_idm_federated_auth(idms: &IdmServer, idms_delayed: &mut IdmServerDelayed) {
+        use compact_jwt::compact::JwkKeySet;
+        use compact_jwt::{JwsEs256Signer, JwsSigner, OidcClaims, OidcSubject, OidcToken};
+        use kanidm_proto::internal::UatPurpose;
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let issuer = Url::parse("https://login.example.com/tenant").expect("Invalid issuer");
+
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup test account");
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let me_mail = ModifyEvent::new_internal_invalid(
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(UUID_TESTPERSON_1))),
+            ModifyList::new_purge_and_set(
+                Attribute::Mail,
+                Value::new_email_address_primary_s("testperson1@example.com")
+                    .expect("Invalid mail address"),
+            ),
+        );
+        assert!(idms_prox_write.qs_write.modify(&me_mail).is_ok());
+
+        let e: Entry<EntryInit, EntryNew> = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::OidcUpstream.to_value()),
+            (Attribute::Name, Value::new_iname("example")),
+            (Attribute::DisplayName, Value::new_utf8s("Example")),
+            (Attribute::OidcUpstreamIssuer, Value::Url(issuer.clone())),
+            (Attribute::OidcUpstreamClientId, Value::new_utf8s("kanidm")),
+            (
+                Attribute::OidcUpstreamClientSecret,
+                Value::SecretValue("secret".to_string())
+            ),
+            (
+                Attribute::OidcUpstreamAuthorisationEndpoint,
+                Value::Url(issuer.join("tenant/authorize").unwrap())
+            ),
+            (
+                Attribute::OidcUpstreamTokenEndpoint,
+                Value::Url(issuer.join("tenant/token").unwrap())
+            ),
+            (
+                Attribute::OidcUpstreamJwksUri,
+                Value::Url(issuer.join("tenant/keys").unwrap())
+            )
+        );
+        let ce = CreateEvent::new_internal(vec![e]);
+        assert!(idms_prox_write.qs_write.create(&ce).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_auth = idms.auth().await.unwrap();
+        let AuthResult { sessionid, state } = idms_auth
+            .auth(
+                &AuthEvent::named_init("testperson1"),
+                ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to init auth session");
+        let AuthState::Choose(mechs) = state else {
+            panic!("Unexpected auth state");
+        };
+        assert!(mechs.contains(&AuthMech::Federated));
+
+        let AuthResult { sessionid, state } = idms_auth
+            .auth(
+                &AuthEvent::begin_mech(sessionid, AuthMech::Federated),
+                ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to begin federated login");
+        let AuthState::Continue(allowed) = state else {
+            panic!("Unexpected auth state");
+        };
+        let Some(AuthAllowed::Federated(providers)) = allowed.first() else {
+            panic!("Upstream providers were not offered");
+        };
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].name, "example");
+
+        let query: std::collections::BTreeMap<_, _> = providers[0]
+            .authorise_url
+            .query_pairs()
+            .into_owned()
+            .collect();
+        let upstream_state = query.get("state").expect("No state").clone();
+        let nonce = query.get("nonce").expect("No nonce").clone();
+        assert_eq!(query.get("code_challenge_method").unwrap(), "S256");
+
+        // A state that wasn't issued to this session is rejected.
+        assert_eq!(
+            idms_auth
+                .auth_federated_exchange(sessionid, "invalid")
+                .await
+                .err(),
+            Some(OperationError::AU0008FederatedRequestInvalid)
+        );
+
+        let exchange = idms_auth
+            .auth_federated_exchange(sessionid, &upstream_state)
+            .await
+            .expect("Failed to exchange");
+        assert_eq!(exchange.client_id, "kanidm");
+        assert_eq!(exchange.redirect_uri.path(), "/ui/login/federated/callback");
+
+        // The provider identifies the person by the mail address of the account.
+        let signer = JwsEs256Signer::generate_es256().expect("Unable to create signer");
+        let jwks = JwkKeySet {
+            keys: vec![signer
+                .public_key_as_jwk()
+                .expect("Unable to get public key")],
+        };
+        let id_token = OidcToken {
+            iss: issuer,
+            sub: OidcSubject::S("upstream-user".to_string()),
+            aud: "kanidm".to_string(),
+            iat: ct.as_secs() as i64,
+            nbf: None,
+            exp: ct.as_secs() as i64 + 300,
+            auth_time: None,
+            nonce: Some(nonce),
+            at_hash: None,
+            acr: None,
+            amr: None,
+            azp: None,
+            jti: None,
+            s_claims: OidcClaims {
+                email: Some("TestPerson1@example.com".to_string()),
+                email_verified: Some(true),
+                ..Default::default()
+            },
+            claims: Default::default(),
+        };
+        let id_token = signer.sign(&id_token).expect("Unable to sign").to_string();
+
+        idms_auth
+            .auth_federated_verify(sessionid, &id_token, &jwks, ct)
+            .await
+            .expect("Failed to verify");
+
+        let AuthResult { state, .. } = idms_auth
+            .auth(
+                &AuthEvent::cred_step_federated(sessionid, &upstream_state),
+                ct,
+                Source::Internal.into(),
+            )
+            .await
+            .expect("Failed to step federated login");
+        let AuthState::Success(token, AuthIssueSession::Token) = state else {
+            panic!("Unexpected auth state");
+        };
+        idms_auth.commit().expect("Must not fail");
+
+        // Upstream logins can not directly grant privileges.
+        let Token::UserAuthToken(uat) = idms
+            .proxy_read()
+            .await
+            .unwrap()
+            .validate_and_parse_token_to_token(&token, ct)
+            .expect("Must not fail")
+        else {
+            panic!("Unexpected auth token type for federated auth");
+        };
+        assert_eq!(uat.purpose, UatPurpose::ReadWrite { expiry: None });
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+        idms_delayed.check_is_empty_or_panic();
+    }
+
+    #[idm_test(audit = 1)]
+    async fn test_idm_break_glass_auth(
+        idms: &IdmServer,
+        _idms_delayed: &IdmServerDelayed,
+        idms_audit: &mut IdmServerAudit,
+    ) {
+        use openssl::ec::{EcGroup, EcKey};
+        use openssl::hash::MessageDigest;
+        use openssl::nid::Nid;
+        use openssl::pkey::PKey;
+        use openssl::sign::Signer;
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).expect("Invalid curve");
+        let key = EcKey::generate(&group)
+            .and_then(PKey::from_ec_key)
+            .expect("Unable to generate key");
+        let sign = |challenge: &[u8]| {
+            let mut signer =
+                Signer::new(MessageDigest::sha256(), &key).expect("Unable to create signer");
+            signer.update(challenge).expect("Unable to sign");
+            signer.sign_to_vec().expect("Unable to sign")
+        };
+
+        // Not enabled until a key is configured.
+        let mut idms_auth = idms.auth().await.unwrap();
+        assert_eq!(
+            idms_auth.break_glass_begin(ct),
+            Err(OperationError::AccessDenied)
+        );
+        drop(idms_auth);
+
+        idms.break_glass_enable(
+            Some(&key.public_key_to_pem().expect("Unable to export key")),
+            None,
+        )
+        .expect("Unable to enable break glass");
+
+        let mut idms_auth = idms.auth().await.unwrap();
+
+        // A bad signature is denied and audited.
+        let (challenge_id, _challenge) = idms_auth.break_glass_begin(ct).expect("Failed to begin");
+        assert!(matches!(
+            idms_auth.break_glass_finish(challenge_id, &sign(b"invalid"), ct, Source::Internal),
+            Err(OperationError::NotAuthenticated)
+        ));
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::BreakGlassDenied { .. }) => {}
+            _ => panic!("Break glass denial was not audited"),
+        }
+
+        let (challenge_id, challenge) = idms_auth.break_glass_begin(ct).expect("Failed to begin");
+        let token = idms_auth
+            .break_glass_finish(challenge_id, &sign(&challenge), ct, Source::Internal)
+            .expect("Failed to finish break glass");
+        idms_auth.commit().expect("Must not fail");
+
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::BreakGlassAuthenticated { uuid, .. }) => assert_eq!(uuid, UUID_ADMIN),
+            _ => panic!("Break glass session was not audited"),
+        }
+
+        // The session is usable as the admin account with write access.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(token.clone().into(), ct)
+            .expect("Unable to use break glass session");
+        assert_eq!(ident.get_uuid(), Some(UUID_ADMIN));
+        assert_eq!(ident.access_scope(), AccessScope::ReadWrite);
+        drop(idms_prox_read);
+
+        // And it expires.
+        let expired = ct + Duration::from_secs(crate::idm::breakglass::BREAK_GLASS_SESSION_EXPIRY);
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        assert_eq!(
+            idms_prox_read
+                .validate_client_auth_info_to_ident(token.into(), expired)
+                .map(|_| ()),
+            Err(OperationError::SessionExpired)
+        );
+    }
+
+    #[idm_test(audit = 1)]
+    async fn test_idm_break_glass_code_auth(
+        idms: &IdmServer,
+        _idms_delayed: &IdmServerDelayed,
+        idms_audit: &mut IdmServerAudit,
+    ) {
+        use crate::idm::breakglass::BreakGlassCodeFile;
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("emergency")),
+            (Attribute::Uuid, Value::Uuid(UUID_TESTPERSON_1)),
+            (Attribute::DisplayName, Value::new_utf8s("Emergency Access"))
+        );
+        assert!(idms_prox_write.qs_write.internal_create(vec![e]).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let path = std::env::temp_dir().join(format!("kanidm-break-glass-{}.json", Uuid::new_v4()));
+        let mut file = BreakGlassCodeFile::default();
+        let codes = file.generate("emergency", 1);
+        file.write(&path).expect("Unable to write codes");
+
+        idms.break_glass_enable(None, Some(&path))
+            .expect("Unable to enable break glass");
+
+        let mut idms_auth = idms.auth().await.unwrap();
+
+        // Challenges need a break glass key.
+        assert_eq!(
+            idms_auth.break_glass_begin(ct),
+            Err(OperationError::AccessDenied)
+        );
+
+        // An invalid code is denied and audited.
+        assert_eq!(
+            idms_auth
+                .break_glass_code("emergency", "invalid", ct, Source::Internal)
+                .map(|_| ()),
+            Err(OperationError::NotAuthenticated)
+        );
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::BreakGlassDenied { account, .. }) => {
+                assert_eq!(account.as_deref(), Some("emergency"))
+            }
+            _ => panic!("Break glass denial was not audited"),
+        }
+
+        let token = idms_auth
+            .break_glass_code("emergency", &codes[0], ct, Source::Internal)
+            .expect("Failed to authenticate with recovery code");
+
+        match idms_audit.audit_rx().try_recv() {
+            Ok(AuditEvent::BreakGlassAuthenticated { uuid, .. }) => {
+                assert_eq!(uuid, UUID_TESTPERSON_1)
+            }
+            _ => panic!("Break glass session was not audited"),
+        }
+
+        // The code can't be used again.
+        assert_eq!(
+            idms_auth
+                .break_glass_code("emergency", &codes[0], ct, Source::Internal)
+                .map(|_| ()),
+            Err(OperationError::NotAuthenticated)
+        );
+        idms_auth.commit().expect("Must not fail");
+
+        // The session is usable as the emergency account.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(token.into(), ct)
+            .expect("Unable to use break glass session");
+        assert_eq!(ident.get_uuid(), Some(UUID_TESTPERSON_1));
+
+        let _ = std::fs::remove_file(&path);
+    }
 }
@@ -8,11 +8,13 @@ use time::OffsetDateTime;
 use crate::credential::Credential;
 use crate::event::SearchEvent;
 use crate::idm::account::Account;
+use crate::idm::audit::AuditEvent;
 use crate::idm::event::GeneratePasswordEvent;
+use crate::idm::jwtassertion::jwt_public_key_from_pem;
 use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction};
 use crate::prelude::*;
 use crate::utils::password_from_random;
-use crate::value::ApiToken;
+use crate::value::{ApiToken, ApiTokenPermission};
 
 macro_rules! try_from_entry {
     ($value:expr) => {{
@@ -126,6 +128,8 @@ pub struct GenerateApiTokenEvent {
     pub expiry: Option<time::OffsetDateTime>,
     // Is it read_write capable?
     pub read_write: bool,
+    // If not empty, what may this token modify?
+    pub permissions: Vec<ApiTokenPermission>,
     // Limits?
 }
 
@@ -138,6 +142,7 @@ impl GenerateApiTokenEvent {
             label: label.to_string(),
             expiry: expiry.map(|ct| time::OffsetDateTime::UNIX_EPOCH + ct),
             read_write: false,
+            permissions: Vec::with_capacity(0),
         }
     }
 }
@@ -162,6 +167,26 @@ impl DestroyApiTokenEvent {
     }
 }
 
+pub struct AddJwtPublicKeyEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Who is it targeting?
+    pub target: Uuid,
+    // The label of the key.
+    pub label: String,
+    // The public key in PEM format.
+    pub public_key_pem: String,
+}
+
+pub struct RemoveJwtPublicKeyEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Who is it targeting?
+    pub target: Uuid,
+    // The label of the key.
+    pub label: String,
+}
+
 impl IdmServerProxyWriteTransaction<'_> {
     pub fn service_account_generate_api_token(
         &mut self,
@@ -190,6 +215,21 @@ impl IdmServerProxyWriteTransaction<'_> {
         };
         let purpose = scope.try_into()?;
 
+        // Permissions only narrow what a read write token may modify.
+        if !gte.permissions.is_empty() && !gte.read_write {
+            error!("Api token permissions require a read write token");
+            return Err(OperationError::InvalidRequestState);
+        }
+
+        if gte
+            .permissions
+            .iter()
+            .any(|p| p.class.is_empty() || p.attrs.is_empty())
+        {
+            error!("Api token permissions must have a class and at least one attribute");
+            return Err(OperationError::InvalidRequestState);
+        }
+
         // create a new session
         let session = Value::ApiToken(
             session_id,
@@ -204,6 +244,7 @@ impl IdmServerProxyWriteTransaction<'_> {
                 // What is the access scope of this session? This is
                 // for auditing purposes.
                 scope,
+                permissions: gte.permissions.clone(),
             },
         );
 
@@ -215,6 +256,8 @@ impl IdmServerProxyWriteTransaction<'_> {
             expiry: gte.expiry,
             issued_at,
             purpose,
+            // These are what the token is held to when it is used.
+            permissions: gte.permissions.iter().map(|p| p.into()).collect(),
         };
 
         let token = Jws::into_json(&proto_api_token).map_err(|err| {
@@ -241,6 +284,16 @@ impl IdmServerProxyWriteTransaction<'_> {
                 err
             })?;
 
+        self.queue_activity_event(AuditEvent::ApiTokenIssued {
+            source: gte.ident.source().clone().into(),
+            actor: gte.ident.get_uuid(),
+            uuid: service_account.uuid,
+            token_id: session_id,
+            label: gte.label.clone(),
+            expiry,
+            time: issued_at,
+        });
+
         self.qs_write
             .get_domain_key_object_handle()?
             .jws_es256_sign(&token, ct)
@@ -284,6 +337,59 @@ impl IdmServerProxyWriteTransaction<'_> {
             })
     }
 
+    /// Register a public key that may sign jwt assertions to authenticate the service account.
+    pub fn service_account_add_jwt_public_key(
+        &mut self,
+        ake: &AddJwtPublicKeyEvent,
+    ) -> Result<(), OperationError> {
+        let der = jwt_public_key_from_pem(&ake.public_key_pem)?;
+
+        let modlist = ModifyList::new_list(vec![Modify::Present(
+            Attribute::JwtPublicKey,
+            Value::new_publicbinary(ake.label.clone(), der),
+        )]);
+
+        self.qs_write
+            .impersonate_modify(
+                // Filter as executed
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(ake.target))),
+                // Filter as intended (acp)
+                &filter_all!(f_eq(Attribute::Uuid, PartialValue::Uuid(ake.target))),
+                &modlist,
+                // Provide the event to impersonate
+                &ake.ident,
+            )
+            .map_err(|e| {
+                admin_error!("Failed to add jwt public key {:?}", e);
+                e
+            })
+    }
+
+    pub fn service_account_remove_jwt_public_key(
+        &mut self,
+        rke: &RemoveJwtPublicKeyEvent,
+    ) -> Result<(), OperationError> {
+        let modlist = ModifyList::new_list(vec![Modify::Removed(
+            Attribute::JwtPublicKey,
+            PartialValue::new_publicbinary_tag_s(&rke.label),
+        )]);
+
+        self.qs_write
+            .impersonate_modify(
+                // Filter as executed
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(rke.target))),
+                // Filter as intended (acp)
+                &filter_all!(f_eq(Attribute::Uuid, PartialValue::Uuid(rke.target))),
+                &modlist,
+                // Provide the event to impersonate
+                &rke.ident,
+            )
+            .map_err(|e| {
+                admin_error!("Failed to remove jwt public key {:?}", e);
+                e
+            })
+    }
+
     pub fn generate_service_account_password(
         &mut self,
         gpe: &GeneratePasswordEvent,
@@ -364,6 +470,11 @@ impl IdmServerProxyReadTransaction<'_> {
                                                 expiry: s.expiry,
                                                 issued_at: s.issued_at,
                                                 purpose,
+                                                permissions: s
+                                                    .permissions
+                                                    .iter()
+                                                    .map(|p| p.into())
+                                                    .collect(),
                                             })
                                             .inspect_err(|err| {
                                                 admin_error!(?err, "Invalid api_token {}", u);
@@ -389,7 +500,13 @@ mod tests {
     use compact_jwt::{dangernoverify::JwsDangerReleaseWithoutVerify, JwsVerifier};
     use kanidm_proto::internal::ApiToken;
 
-    use super::{DestroyApiTokenEvent, GenerateApiTokenEvent};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+
+    use super::{
+        AddJwtPublicKeyEvent, DestroyApiTokenEvent, GenerateApiTokenEvent, RemoveJwtPublicKeyEvent,
+    };
+    use crate::idm::delayed::DelayedAction;
     use crate::idm::server::IdmServerTransaction;
     use crate::prelude::*;
 
@@ -398,7 +515,7 @@ mod tests {
     #[idm_test]
     async fn test_idm_service_account_api_token(
         idms: &IdmServer,
-        _idms_delayed: &mut IdmServerDelayed,
+        idms_delayed: &mut IdmServerDelayed,
     ) {
         let ct = Duration::from_secs(TEST_CURRENT_TIME);
         let past_grc = Duration::from_secs(TEST_CURRENT_TIME + 1) + AUTH_TOKEN_GRACE_WINDOW;
@@ -479,5 +596,88 @@ mod tests {
         );
 
         assert!(idms_prox_write.commit().is_ok());
+
+        // Each use of the api token is recorded.
+        while let Ok(da) = idms_delayed.try_recv() {
+            assert!(matches!(da, DelayedAction::ApiTokenUse(_)));
+        }
+    }
+
+    #[idm_test]
+    async fn test_idm_service_account_jwt_public_key(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let testaccount_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_account_only")),
+            (Attribute::Uuid, Value::Uuid(testaccount_uuid)),
+            (Attribute::Description, Value::new_utf8s("testaccount")),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount"))
+        );
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e1])
+            .expect("Failed to create service account");
+
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).expect("Invalid curve");
+        let key = EcKey::generate(&group).expect("Unable to generate key");
+        let public_key_pem = key.public_key_to_pem().expect("Unable to export key");
+
+        let ake = AddJwtPublicKeyEvent {
+            ident: Identity::from_internal(),
+            target: testaccount_uuid,
+            label: "deploy".to_string(),
+            public_key_pem: String::from_utf8(public_key_pem).expect("Invalid pem"),
+        };
+        assert!(idms_prox_write
+            .service_account_add_jwt_public_key(&ake)
+            .is_ok());
+
+        // Keys that can't sign assertions are rejected.
+        let bad_ake = AddJwtPublicKeyEvent {
+            label: "other".to_string(),
+            public_key_pem: "not a key".to_string(),
+            ..ake
+        };
+        assert!(idms_prox_write
+            .service_account_add_jwt_public_key(&bad_ake)
+            .is_err());
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(testaccount_uuid)
+            .expect("Unable to find service account");
+        let keys = entry
+            .get_ava_set(Attribute::JwtPublicKey)
+            .and_then(|vs| vs.as_publicbinary_map())
+            .expect("No keys present");
+        assert!(keys.contains_key("deploy"));
+        assert_eq!(keys.len(), 1);
+
+        let rke = RemoveJwtPublicKeyEvent {
+            ident: Identity::from_internal(),
+            target: testaccount_uuid,
+            label: "deploy".to_string(),
+        };
+        assert!(idms_prox_write
+            .service_account_remove_jwt_public_key(&rke)
+            .is_ok());
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(testaccount_uuid)
+            .expect("Unable to find service account");
+        assert!(entry.get_ava_set(Attribute::JwtPublicKey).is_none());
+
+        assert!(idms_prox_write.commit().is_ok());
     }
 }
@@ -0,0 +1,290 @@
+//! Binding of browser sessions to a key held by the browser. The browser creates a key pair
+//! with WebCrypto that can't be exported, and keeps a short lived proof signed by that key in a
+//! cookie. Sessions issued while a proof is presented carry the thumbprint of the key, and each
+//! request that uses such a session must present a current proof from the same key. A stolen
+//! session cookie is then useless once its proof expires, since the key can't leave the browser.
+//!
+//! Every session from a browser is bound to the same key, so once the browser knows the id of its
+//! session, it is included in the proof. A proof can then only be used with that session, and not
+//! with an older session of the same browser whose token was stolen. Until then, such as on the
+//! first request after a login, a proof without a session id is only accepted if it was made
+//! before the session was issued.
+
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use compact_jwt::{JwsCompact, JwsEs256Verifier, JwsVerifier};
+use kanidm_proto::internal::UserAuthToken;
+use serde::{Deserialize, Serialize};
+
+use crate::idm::dpop::{jwk_thumbprint, DPoPProofHeader, DPOP_PROOF_ALG};
+use crate::idm::ClientAuthInfo;
+use crate::prelude::*;
+
+pub const SESSION_PROOF_TYP: &str = "kanidm-session+jwt";
+
+/// A proof that the client holds the key that its session is bound to.
+#[derive(Debug, Clone)]
+pub struct SessionProof(pub String);
+
+#[derive(Serialize, Deserialize)]
+struct SessionProofClaims {
+    iat: i64,
+    /// The session that this proof is for, once the browser knows it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sid: Option<Uuid>,
+}
+
+/// The content of a session proof with a valid signature.
+pub(crate) struct VerifiedSessionProof {
+    /// The thumbprint of the key that signed the proof.
+    jkt: String,
+    iat: i64,
+    sid: Option<Uuid>,
+}
+
+impl SessionProof {
+    /// Verify this proof, returning the thumbprint of the key that signed it and its claims.
+    pub(crate) fn verify(&self, ct: Duration) -> Result<VerifiedSessionProof, OperationError> {
+        let proof = JwsCompact::from_str(&self.0).map_err(|err| {
+            security_info!(?err, "Session proof is not a valid jws");
+            OperationError::NotAuthenticated
+        })?;
+
+        let header = self
+            .0
+            .split('.')
+            .next()
+            .and_then(|header| URL_SAFE_NO_PAD.decode(header).ok())
+            .and_then(|header| serde_json::from_slice::<DPoPProofHeader>(&header).ok())
+            .ok_or_else(|| {
+                security_info!("Session proof header is invalid");
+                OperationError::NotAuthenticated
+            })?;
+
+        if header.typ.as_deref() != Some(SESSION_PROOF_TYP) || header.alg != DPOP_PROOF_ALG {
+            security_info!(typ = ?header.typ, alg = %header.alg, "Session proof type or algorithm is not supported");
+            return Err(OperationError::NotAuthenticated);
+        }
+
+        let jwk = proof.get_jwk_pubkey().ok_or_else(|| {
+            security_info!("Session proof does not contain a public key");
+            OperationError::NotAuthenticated
+        })?;
+
+        let claims: SessionProofClaims = JwsEs256Verifier::try_from(jwk)
+            .and_then(|verifier| verifier.verify(&proof))
+            .and_then(|jws| jws.from_json())
+            .map_err(|err| {
+                security_info!(?err, "Session proof signature is invalid");
+                OperationError::NotAuthenticated
+            })?;
+
+        let max_age = SESSION_PROOF_MAX_AGE as i64;
+        let max_skew = SESSION_PROOF_MAX_SKEW as i64;
+        let now = ct.as_secs() as i64;
+        if claims.iat < now - max_age || claims.iat > now + max_skew {
+            security_info!(iat = %claims.iat, %now, "Session proof is not within its validity window");
+            return Err(OperationError::NotAuthenticated);
+        }
+
+        let jkt = jwk_thumbprint(jwk).map_err(|_| OperationError::NotAuthenticated)?;
+
+        Ok(VerifiedSessionProof {
+            jkt,
+            iat: claims.iat,
+            sid: claims.sid,
+        })
+    }
+}
+
+/// The key that a session issued to this client is bound to. If the client did not present a
+/// valid proof the session is not bound.
+pub(crate) fn session_key_for(client_auth_info: &ClientAuthInfo, ct: Duration) -> Option<String> {
+    client_auth_info
+        .session_proof
+        .as_ref()
+        .and_then(|proof| proof.verify(ct).ok())
+        .map(|proof| proof.jkt)
+}
+
+/// Check that a session which is bound to a key was presented with a proof from that key, made
+/// for this session.
+pub(crate) fn check_session_binding(
+    uat: &UserAuthToken,
+    session_proof: Option<&SessionProof>,
+    ct: Duration,
+) -> Result<(), OperationError> {
+    let Some(session_key) = uat.session_key.as_deref() else {
+        // Not bound.
+        return Ok(());
+    };
+
+    let Some(session_proof) = session_proof else {
+        security_info!("Session is bound to a key, but no proof was presented");
+        return Err(OperationError::NotAuthenticated);
+    };
+
+    let proof = session_proof.verify(ct)?;
+
+    if proof.jkt != session_key {
+        security_info!("Session proof was signed by a different key");
+        return Err(OperationError::NotAuthenticated);
+    }
+
+    match proof.sid {
+        Some(sid) if sid == uat.session_id => Ok(()),
+        Some(sid) => {
+            security_info!(%sid, session_id = %uat.session_id, "Session proof was made for a different session");
+            Err(OperationError::NotAuthenticated)
+        }
+        None if proof.iat <= uat.issued_at.unix_timestamp() + SESSION_PROOF_MAX_SKEW as i64 => {
+            Ok(())
+        }
+        None => {
+            security_info!(session_id = %uat.session_id, "Session proof without a session was made after the session was issued");
+            Err(OperationError::NotAuthenticated)
+        }
+    }
+}
+
+/// Create a session proof as a browser would.
+#[cfg(test)]
+pub(crate) fn test_session_proof(
+    signer: &compact_jwt::JwsEs256Signer,
+    ct: Duration,
+    sid: Option<Uuid>,
+) -> (SessionProof, String) {
+    use compact_jwt::{jws::JwsBuilder, JwsSigner};
+
+    let claims = SessionProofClaims {
+        iat: ct.as_secs() as i64,
+        sid,
+    };
+
+    let jws = JwsBuilder::into_json(&claims)
+        .map(|builder| builder.set_typ(Some(SESSION_PROOF_TYP)).build())
+        .expect("Unable to build proof");
+
+    let proof = signer.sign(&jws).expect("Unable to sign proof");
+
+    let jwk = signer
+        .public_key_as_jwk()
+        .expect("Unable to get public key");
+
+    (
+        SessionProof(proof.to_string()),
+        jwk_thumbprint(&jwk).expect("Unable to create thumbprint"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_session_binding, test_session_proof};
+    use crate::prelude::*;
+    use compact_jwt::JwsEs256Signer;
+    use kanidm_proto::internal::{UatPurpose, UserAuthToken};
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    fn test_signer() -> JwsEs256Signer {
+        JwsEs256Signer::generate_es256()
+            .expect("Unable to create signer")
+            .set_sign_option_embed_jwk(true)
+    }
+
+    fn test_uat(session_key: Option<String>, ct: Duration) -> UserAuthToken {
+        UserAuthToken {
+            session_id: Uuid::new_v4(),
+            expiry: None,
+            issued_at: time::OffsetDateTime::UNIX_EPOCH + ct,
+            purpose: UatPurpose::ReadOnly,
+            uuid: UUID_TESTPERSON_1,
+            displayname: "Test Person".to_string(),
+            spn: "testperson1@example.com".to_string(),
+            mail_primary: None,
+            ui_hints: Default::default(),
+            limit_search_max_results: None,
+            limit_search_max_filter_test: None,
+            impersonated_by: None,
+            session_key,
+            idle_timeout: None,
+        }
+    }
+
+    #[test]
+    fn test_session_binding_check() {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let signer = test_signer();
+        let (proof, jkt) = test_session_proof(&signer, ct, None);
+
+        assert_eq!(proof.verify(ct).map(|proof| proof.jkt), Ok(jkt.clone()));
+
+        // Unbound sessions don't need a proof.
+        assert!(check_session_binding(&test_uat(None, ct), None, ct).is_ok());
+
+        let uat = test_uat(Some(jkt.clone()), ct);
+        assert!(check_session_binding(&uat, Some(&proof), ct).is_ok());
+        assert_eq!(
+            check_session_binding(&uat, None, ct),
+            Err(OperationError::NotAuthenticated)
+        );
+
+        // A proof from another key.
+        let (other_proof, _) = test_session_proof(&test_signer(), ct, Some(uat.session_id));
+        assert_eq!(
+            check_session_binding(&uat, Some(&other_proof), ct),
+            Err(OperationError::NotAuthenticated)
+        );
+
+        // An expired proof.
+        let later = ct + Duration::from_secs(SESSION_PROOF_MAX_AGE + 1);
+        assert_eq!(
+            check_session_binding(&uat, Some(&proof), later),
+            Err(OperationError::NotAuthenticated)
+        );
+
+        // A proof from too far in the future.
+        let (future_proof, _) = test_session_proof(
+            &signer,
+            ct + Duration::from_secs(SESSION_PROOF_MAX_SKEW + 1),
+            Some(uat.session_id),
+        );
+        assert_eq!(
+            check_session_binding(&uat, Some(&future_proof), ct),
+            Err(OperationError::NotAuthenticated)
+        );
+    }
+
+    #[test]
+    fn test_session_binding_session_id() {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let later = ct + Duration::from_secs(60);
+        let signer = test_signer();
+        let (_, jkt) = test_session_proof(&signer, ct, None);
+        let uat = test_uat(Some(jkt), ct);
+
+        // Once the session is issued, a proof must be for this session.
+        let (proof, _) = test_session_proof(&signer, later, Some(uat.session_id));
+        assert!(check_session_binding(&uat, Some(&proof), later).is_ok());
+
+        let (proof, _) = test_session_proof(&signer, later, None);
+        assert_eq!(
+            check_session_binding(&uat, Some(&proof), later),
+            Err(OperationError::NotAuthenticated)
+        );
+
+        // An older session of the same browser can't be used with this proof.
+        let old_uat = test_uat(uat.session_key.clone(), ct - Duration::from_secs(3600));
+        let (proof, _) = test_session_proof(&signer, later, Some(uat.session_id));
+        assert_eq!(
+            check_session_binding(&old_uat, Some(&proof), later),
+            Err(OperationError::NotAuthenticated)
+        );
+        let (proof, _) = test_session_proof(&signer, ct, None);
+        assert_eq!(
+            check_session_binding(&old_uat, Some(&proof), ct),
+            Err(OperationError::NotAuthenticated)
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Persons may review the sessions that they have signed in with, and the sessions that they
+//! have with OAuth2 clients, and revoke any that they don't recognise.
+
+use kanidm_proto::internal::{SelfOauth2Session, SelfSession, SelfSessions};
+use time::OffsetDateTime;
+
+use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction};
+use crate::prelude::*;
+use crate::value::{CredentialFactor, SessionState};
+
+pub struct RevokeSelfSessionEvent {
+    // Who initiated this? The session must belong to this person.
+    pub ident: Identity,
+    // The session to revoke. This may be an auth session or an oauth2 session.
+    pub session_id: Uuid,
+}
+
+/// If a session is still usable, and when it expires.
+fn session_active(state: &SessionState, ct: Duration) -> Option<Option<OffsetDateTime>> {
+    match state {
+        SessionState::ExpiresAt(expiry) if *expiry > OffsetDateTime::UNIX_EPOCH + ct => {
+            Some(Some(*expiry))
+        }
+        SessionState::NeverExpires => Some(None),
+        SessionState::ExpiresAt(_) | SessionState::RevokedAt(_) => None,
+    }
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub fn list_self_sessions(
+        &mut self,
+        ident: &Identity,
+        ct: Duration,
+    ) -> Result<SelfSessions, OperationError> {
+        let Some(target) = ident.get_uuid() else {
+            error!("Only persons may list their own sessions");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let entry = self.qs_read.internal_search_uuid(target)?;
+
+        let usage = entry.get_ava_as_credential_usage_map(Attribute::CredentialUsage);
+        let current_session_id = ident.get_session_id();
+
+        let mut sessions = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .into_iter()
+            .flatten()
+            .filter_map(|(session_id, session)| {
+                let expiry = session_active(&session.state, ct)?;
+                let session_usage =
+                    usage.and_then(|usage| usage.get(&(*session_id, CredentialFactor::Session)));
+
+                Some(SelfSession {
+                    session_id: *session_id,
+                    issued_at: session.issued_at,
+                    expiry,
+                    current: *session_id == current_session_id,
+                    source: session_usage.and_then(|usage| usage.source.clone()),
+                    user_agent: session_usage.and_then(|usage| usage.user_agent.clone()),
+                    last_used: session_usage.map(|usage| usage.last_used),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // The most recently created sessions first.
+        sessions.sort_unstable_by(|a, b| b.issued_at.cmp(&a.issued_at));
+
+        let mut oauth2_sessions = entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .into_iter()
+            .flatten()
+            .filter_map(|(session_id, session)| {
+                let expiry = session_active(&session.state, ct)?;
+
+                // The client may have been deleted since the session was issued.
+                let client = self.qs_read.internal_search_uuid(session.rs_uuid).ok()?;
+
+                let client_name = client
+                    .get_ava_single_iname(Attribute::Name)
+                    .map(str::to_string)?;
+                let display_name = client
+                    .get_ava_single_utf8(Attribute::DisplayName)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| client_name.clone());
+
+                Some(SelfOauth2Session {
+                    session_id: *session_id,
+                    client_name,
+                    display_name,
+                    issued_at: session.issued_at,
+                    expiry,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        oauth2_sessions.sort_unstable_by(|a, b| b.issued_at.cmp(&a.issued_at));
+
+        Ok(SelfSessions {
+            sessions,
+            oauth2_sessions,
+        })
+    }
+}
+
+impl IdmServerProxyWriteTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub fn revoke_self_session(
+        &mut self,
+        rse: &RevokeSelfSessionEvent,
+    ) -> Result<(), OperationError> {
+        let Some(target) = rse.ident.get_uuid() else {
+            error!("Only persons may revoke their own sessions");
+            return Err(OperationError::AccessDenied);
+        };
+
+        let entry = self.qs_write.internal_search_uuid(target)?;
+
+        let is_active = |state: &SessionState| !matches!(state, SessionState::RevokedAt(_));
+
+        let attr = if entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|sessions| sessions.get(&rse.session_id))
+            .is_some_and(|session| is_active(&session.state))
+        {
+            Attribute::UserAuthTokenSession
+        } else if entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .and_then(|sessions| sessions.get(&rse.session_id))
+            .is_some_and(|session| is_active(&session.state))
+        {
+            Attribute::OAuth2Session
+        } else {
+            error!(session_id = %rse.session_id, "No active session exists with this id");
+            return Err(OperationError::NoMatchingEntries);
+        };
+
+        let modlist = ModifyList::new_list(vec![Modify::Removed(
+            attr,
+            PartialValue::Refer(rse.session_id),
+        )]);
+
+        // Revoking a session only ever reduces access, so this is permitted regardless of
+        // the access controls on the person.
+        self.qs_write
+            .internal_modify_uuid(target, &modlist)
+            .map_err(|err| {
+                error!(?err, "Failed to revoke session");
+                err
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RevokeSelfSessionEvent;
+    use crate::idm::delayed::{AuthSessionRecord, DelayedAction};
+    use crate::prelude::*;
+    use crate::value::{AuthType, SessionScope};
+    use time::OffsetDateTime;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[idm_test]
+    async fn test_idm_self_sessions(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let target_uuid = Uuid::new_v4();
+
+        let e_usr = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Uuid, Value::Uuid(target_uuid)),
+            (Attribute::Name, Value::new_iname("kevin")),
+            (Attribute::DisplayName, Value::new_utf8s("Kevin"))
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![e_usr])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let session_id = Uuid::new_v4();
+        let da = DelayedAction::AuthSessionRecord(AuthSessionRecord {
+            target_uuid,
+            session_id,
+            cred_id: Uuid::new_v4(),
+            label: "Auth Session".to_string(),
+            expiry: Some(OffsetDateTime::UNIX_EPOCH + ct + Duration::from_secs(3600)),
+            issued_at: OffsetDateTime::UNIX_EPOCH + ct,
+            issued_by: IdentityId::User(target_uuid),
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            factors: Vec::new(),
+            source: Some("203.0.113.1".to_string()),
+            user_agent: Some("Mozilla/5.0 (X11; Linux x86_64)".to_string()),
+        });
+        assert_eq!(idms.delayed_action(ct, da).await, Ok(true));
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let entry = idms_prox_read
+            .qs_read
+            .internal_search_uuid(target_uuid)
+            .expect("Unable to find person");
+        let ident = Identity::from_impersonate_entry_readonly(entry);
+
+        let self_sessions = idms_prox_read
+            .list_self_sessions(&ident, ct)
+            .expect("Unable to list sessions");
+        assert_eq!(self_sessions.sessions.len(), 1);
+        let session = &self_sessions.sessions[0];
+        assert_eq!(session.session_id, session_id);
+        assert!(!session.current);
+        assert_eq!(session.source.as_deref(), Some("203.0.113.1"));
+        assert_eq!(
+            session.user_agent.as_deref(),
+            Some("Mozilla/5.0 (X11; Linux x86_64)")
+        );
+        assert_eq!(session.last_used, Some(OffsetDateTime::UNIX_EPOCH + ct));
+        assert!(self_sessions.oauth2_sessions.is_empty());
+
+        // Once expired, the session is no longer listed.
+        let self_sessions = idms_prox_read
+            .list_self_sessions(&ident, ct + Duration::from_secs(7200))
+            .expect("Unable to list sessions");
+        assert!(self_sessions.sessions.is_empty());
+        drop(idms_prox_read);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let rse = RevokeSelfSessionEvent {
+            ident: ident.clone(),
+            session_id,
+        };
+        assert!(idms_prox_write.revoke_self_session(&rse).is_ok());
+
+        // Once revoked, it can't be revoked again.
+        assert_eq!(
+            idms_prox_write.revoke_self_session(&rse),
+            Err(OperationError::NoMatchingEntries)
+        );
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let self_sessions = idms_prox_read
+            .list_self_sessions(&ident, ct)
+            .expect("Unable to list sessions");
+        assert!(self_sessions.sessions.is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use crate::idm::account::Account;
+use crate::idm::audit::AuditEvent;
+use crate::idm::event::SshCertificateIssueEvent;
+use crate::idm::group::load_account_policy;
+use crate::idm::server::IdmServerProxyWriteTransaction;
+use crate::prelude::*;
+use sshkey_attest::proto::PublicKey as SshPublicKey;
+
+impl IdmServerProxyWriteTransaction<'_> {
+    /// Ensure that the domain key object is able to issue ssh certificates. The certificate
+    /// authority is created on first use.
+    fn ssh_ca_assert(&mut self) -> Result<(), OperationError> {
+        let domain_entry = self.qs_write.internal_search_uuid(UUID_DOMAIN_INFO)?;
+
+        if domain_entry.attribute_equality(Attribute::Class, &EntryClass::KeyObjectSshCa.into()) {
+            return Ok(());
+        }
+
+        debug!("Adding ssh certificate authority to domain key object");
+
+        self.qs_write.internal_modify_uuid(
+            UUID_DOMAIN_INFO,
+            &ModifyList::new_append(Attribute::Class, EntryClass::KeyObjectSshCa.into()),
+        )?;
+
+        // Load the new authority into the key providers.
+        self.qs_write.reload()
+    }
+
+    /// The public keys of the ssh certificate authorities that sshd must trust with
+    /// `TrustedUserCAKeys`. Certificates issued before a rotation remain valid, so the
+    /// retained authorities are included.
+    pub fn ssh_ca_public_keys(&mut self) -> Result<Vec<String>, OperationError> {
+        self.ssh_ca_assert()?;
+
+        self.qs_write
+            .get_domain_key_object_handle()?
+            .ssh_ca_public_keys()
+    }
+
+    /// Issue a short lived ssh user certificate to the requesting account. The principals of
+    /// the certificate are the name of the account and the names of the groups it is a member
+    /// of, so that sshd can authorise logins with `AuthorizedPrincipalsFile`.
+    #[instrument(level = "debug", skip_all)]
+    pub fn issue_ssh_certificate(
+        &mut self,
+        scie: &SshCertificateIssueEvent,
+        ct: Duration,
+    ) -> Result<String, OperationError> {
+        let Some(target) = scie.ident.get_uuid() else {
+            error!("Only accounts may request ssh certificates");
+            return Err(OperationError::AccessDenied);
+        };
+
+        if target == UUID_ANONYMOUS {
+            error!("Anonymous may not request ssh certificates");
+            return Err(OperationError::AccessDenied);
+        }
+
+        // A certificate is a credential, so this requires the same privilege as changing the
+        // credentials of the account.
+        if scie.ident.access_scope() != AccessScope::ReadWrite {
+            error!("Requesting an ssh certificate requires a read-write session");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let public_key = SshPublicKey::from_string(&scie.public_key).map_err(|err| {
+            debug!(?err, "Invalid ssh public key");
+            OperationError::VL0001ValueSshPublicKeyString
+        })?;
+
+        let account_entry = self.qs_write.internal_search_uuid(target)?;
+        let account = Account::try_from_entry_rw(&account_entry, &mut self.qs_write)?;
+
+        if !account.is_within_valid_time(ct) {
+            error!("Account is expired or not yet valid");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let account_policy = load_account_policy(&account_entry, &mut self.qs_write)?;
+        if !account_policy.ssh_key_permitted(&public_key) {
+            error!("SSH Public Key is not permitted by the account policy");
+            return Err(OperationError::PL0003SshPublicKeyPolicyDenied);
+        }
+
+        // Even if the account is a member of no groups, the be will just give an empty result.
+        let memberof = account_entry
+            .get_ava_refer(Attribute::MemberOf)
+            .cloned()
+            .unwrap_or_default();
+        let memberof_filter = filter!(f_or(
+            memberof
+                .into_iter()
+                .map(|u| f_eq(Attribute::Uuid, PartialValue::Uuid(u)))
+                .collect()
+        ));
+
+        let mut principals: Vec<String> = self
+            .qs_write
+            .internal_search(memberof_filter)?
+            .iter()
+            .filter_map(|group| group.get_ava_single_iname(Attribute::Name))
+            .map(str::to_string)
+            .collect();
+        principals.sort_unstable();
+        principals.insert(0, account.name.clone());
+
+        // The certificate can't outlive the account.
+        let valid_after = ct.saturating_sub(Duration::from_secs(SSH_USER_CERTIFICATE_BACKDATE));
+        let mut valid_before = ct + Duration::from_secs(SSH_USER_CERTIFICATE_VALIDITY);
+        if let Some(expire) = account.expire {
+            let expire = Duration::from_secs(expire.unix_timestamp().max(0) as u64);
+            valid_before = valid_before.min(expire);
+        }
+
+        self.ssh_ca_assert()?;
+
+        let (serial, certificate) = self
+            .qs_write
+            .get_domain_key_object_handle()?
+            .ssh_user_certificate_issue(
+                &scie.public_key,
+                &account.spn,
+                &principals,
+                valid_after,
+                valid_before,
+            )?;
+
+        security_info!(?serial, spn = %account.spn, "Issued ssh user certificate");
+
+        self.queue_activity_event(AuditEvent::SshCertificateIssued {
+            source: scie.ident.source().clone().into(),
+            uuid: account.uuid,
+            spn: account.spn.clone(),
+            serial,
+            principals,
+            valid_before: OffsetDateTime::UNIX_EPOCH + valid_before,
+            time: OffsetDateTime::UNIX_EPOCH + ct,
+        });
+
+        Ok(certificate)
+    }
+}
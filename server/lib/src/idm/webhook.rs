@@ -0,0 +1,379 @@
+//! Webhooks are urls that are sent an event when entries matching their filters are changed.
+//! Events are derived from the activity audit events, so activity auditing must be enabled for
+//! webhooks to be sent anything. The events only identify the entries and attributes that
+//! changed, never their values, so that a webhook can't be used to observe data that the
+//! receiver isn't otherwise able to read.
+
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use kanidm_proto::internal::WebhookAction;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::idm::audit::AuditEvent;
+use crate::idm::server::IdmServerProxyReadTransaction;
+use crate::prelude::*;
+
+/// Matches any class in a webhook filter.
+const WEBHOOK_FILTER_ANY_CLASS: &str = "*";
+
+/// A filter selecting which entry changes are sent to a webhook, in the form
+/// `action:class[:attribute]`. The attribute is only valid for modifications, and limits
+/// the filter to modifications of that attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookFilter {
+    pub action: WebhookAction,
+    pub class: Option<String>,
+    pub attr: Option<Attribute>,
+}
+
+impl FromStr for WebhookFilter {
+    type Err = OperationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || OperationError::InvalidAttribute(format!("Invalid webhook filter {s}"));
+
+        let mut parts = s.split(':');
+
+        let action = parts
+            .next()
+            .and_then(|action| WebhookAction::from_str(action).ok())
+            .ok_or_else(invalid)?;
+
+        let class = match parts.next() {
+            Some(WEBHOOK_FILTER_ANY_CLASS) => None,
+            Some(class) if !class.is_empty() => Some(class.to_string()),
+            _ => return Err(invalid()),
+        };
+
+        let attr = match parts.next() {
+            Some(attr) if !attr.is_empty() && action == WebhookAction::Modified => {
+                Some(Attribute::from(attr))
+            }
+            None => None,
+            _ => return Err(invalid()),
+        };
+
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(WebhookFilter {
+            action,
+            class,
+            attr,
+        })
+    }
+}
+
+impl WebhookFilter {
+    pub(crate) fn matches(
+        &self,
+        action: WebhookAction,
+        classes: &BTreeSet<String>,
+        attrs: &BTreeSet<Attribute>,
+    ) -> bool {
+        self.action == action
+            && self
+                .class
+                .as_ref()
+                .map_or(true, |class| classes.contains(class))
+            && self.attr.as_ref().map_or(true, |attr| attrs.contains(attr))
+    }
+}
+
+/// The body of a webhook event.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookPayload {
+    pub id: Uuid,
+    pub action: WebhookAction,
+    pub target: Uuid,
+    pub classes: BTreeSet<String>,
+    /// The attributes that were changed. This is empty for creations and deletions.
+    pub attrs: BTreeSet<Attribute>,
+    pub actor: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+}
+
+/// An event that is to be sent to a webhook.
+#[derive(Debug, Clone)]
+pub struct WebhookDispatch {
+    pub webhook: Uuid,
+    pub url: Url,
+    pub secret: String,
+    pub payload: WebhookPayload,
+}
+
+/// The creation, modification or deletion of entries, as described by an activity audit event.
+pub(crate) struct EntryChange<'a> {
+    pub action: WebhookAction,
+    pub actor: Uuid,
+    pub targets: &'a BTreeSet<Uuid>,
+    /// The attributes that were changed. This is empty for creations and deletions.
+    pub attrs: &'a BTreeSet<Attribute>,
+    pub time: OffsetDateTime,
+}
+
+impl<'a> EntryChange<'a> {
+    pub(crate) fn from_event(
+        event: &'a AuditEvent,
+        empty_attrs: &'a BTreeSet<Attribute>,
+    ) -> Option<Self> {
+        let (action, actor, targets, attrs, time) = match event {
+            AuditEvent::EntriesCreated {
+                actor,
+                targets,
+                time,
+                ..
+            } => (WebhookAction::Created, actor, targets, empty_attrs, time),
+            AuditEvent::EntriesModified {
+                actor,
+                targets,
+                attrs,
+                time,
+                ..
+            } => (WebhookAction::Modified, actor, targets, attrs, time),
+            AuditEvent::EntriesDeleted {
+                actor,
+                targets,
+                time,
+                ..
+            } => (WebhookAction::Deleted, actor, targets, empty_attrs, time),
+            _ => return None,
+        };
+
+        Some(EntryChange {
+            action,
+            actor: *actor,
+            targets,
+            attrs,
+            time: *time,
+        })
+    }
+}
+
+struct Webhook {
+    uuid: Uuid,
+    url: Url,
+    secret: String,
+    filters: Vec<WebhookFilter>,
+}
+
+impl IdmServerProxyReadTransaction<'_> {
+    fn webhooks(&mut self) -> Result<Vec<Webhook>, OperationError> {
+        let filter = filter!(f_eq(Attribute::Class, EntryClass::Webhook.into()));
+
+        let entries = self.qs_read.internal_search(filter)?;
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| {
+                let url = entry.get_ava_single_url(Attribute::WebhookUrl)?.clone();
+                let secret = entry
+                    .get_ava_single_secret(Attribute::WebhookSecret)?
+                    .to_string();
+                let filters = entry
+                    .get_ava_iter_iutf8(Attribute::WebhookFilter)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|filter| WebhookFilter::from_str(filter).ok())
+                    .collect();
+
+                Some(Webhook {
+                    uuid: entry.get_uuid(),
+                    url,
+                    secret,
+                    filters,
+                })
+            })
+            .collect())
+    }
+
+    /// Determine the events that must be sent to webhooks as a result of this activity. Only
+    /// the creation, modification and deletion of entries are sent to webhooks.
+    pub fn webhook_dispatches(
+        &mut self,
+        event: &AuditEvent,
+    ) -> Result<Vec<WebhookDispatch>, OperationError> {
+        let empty_attrs = BTreeSet::new();
+
+        let Some(EntryChange {
+            action,
+            actor,
+            targets,
+            attrs,
+            time,
+        }) = EntryChange::from_event(event, &empty_attrs)
+        else {
+            return Ok(Vec::with_capacity(0));
+        };
+
+        let webhooks = self.webhooks()?;
+
+        if webhooks.iter().all(|webhook| webhook.filters.is_empty()) {
+            return Ok(Vec::with_capacity(0));
+        }
+
+        let mut dispatches = Vec::new();
+
+        for target in targets.iter().copied() {
+            // Deleted entries are in the recycle bin, and still have their classes.
+            let entry = match self.qs_read.internal_search_all_uuid(target) {
+                Ok(entry) => entry,
+                Err(OperationError::NoMatchingEntries) => {
+                    debug!(
+                        ?target,
+                        "Entry no longer exists, unable to send to webhooks"
+                    );
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            let classes: BTreeSet<String> = entry
+                .get_ava_iter_iutf8(Attribute::Class)
+                .into_iter()
+                .flatten()
+                .map(str::to_string)
+                .collect();
+
+            for webhook in webhooks.iter() {
+                if !webhook
+                    .filters
+                    .iter()
+                    .any(|filter| filter.matches(action, &classes, attrs))
+                {
+                    continue;
+                }
+
+                dispatches.push(WebhookDispatch {
+                    webhook: webhook.uuid,
+                    url: webhook.url.clone(),
+                    secret: webhook.secret.clone(),
+                    payload: WebhookPayload {
+                        id: Uuid::new_v4(),
+                        action,
+                        target,
+                        classes: classes.clone(),
+                        attrs: attrs.clone(),
+                        actor,
+                        time,
+                    },
+                });
+            }
+        }
+
+        Ok(dispatches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
+
+    use kanidm_proto::internal::WebhookAction;
+    use time::OffsetDateTime;
+
+    use super::WebhookFilter;
+    use crate::idm::audit::{AuditEvent, AuditSource};
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[test]
+    fn test_idm_webhook_filter() {
+        let filter = WebhookFilter::from_str("modified:group:member").expect("Invalid filter");
+        assert_eq!(filter.action, WebhookAction::Modified);
+        assert_eq!(filter.class.as_deref(), Some("group"));
+        assert_eq!(filter.attr, Some(Attribute::Member));
+
+        let filter = WebhookFilter::from_str("created:*").expect("Invalid filter");
+        assert_eq!(filter.class, None);
+
+        // Attributes can only be used to filter modifications.
+        assert!(WebhookFilter::from_str("created:person:mail").is_err());
+        assert!(WebhookFilter::from_str("created").is_err());
+        assert!(WebhookFilter::from_str("renamed:person").is_err());
+        assert!(WebhookFilter::from_str("modified:group:member:extra").is_err());
+    }
+
+    #[idm_test]
+    async fn test_idm_webhook_dispatches(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let time = OffsetDateTime::UNIX_EPOCH + ct;
+
+        let webhook_uuid = Uuid::new_v4();
+        let group_uuid = Uuid::new_v4();
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let webhook = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Webhook.to_value()),
+            (Attribute::Name, Value::new_iname("test_webhook")),
+            (Attribute::Uuid, Value::Uuid(webhook_uuid)),
+            (
+                Attribute::WebhookUrl,
+                Value::Url(Url::parse("https://hooks.example.com/kanidm").unwrap())
+            ),
+            (
+                Attribute::WebhookFilter,
+                Value::new_iutf8("modified:group:member")
+            )
+        );
+
+        let group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("test_group")),
+            (Attribute::Uuid, Value::Uuid(group_uuid))
+        );
+
+        assert!(idms_prox_write
+            .qs_write
+            .internal_create(vec![webhook, group])
+            .is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let modified = |attr: Attribute| AuditEvent::EntriesModified {
+            source: AuditSource::Internal,
+            actor: UUID_ADMIN,
+            targets: BTreeSet::from([group_uuid]),
+            attrs: BTreeSet::from([attr]),
+            time,
+        };
+
+        let dispatches = idms_prox_read
+            .webhook_dispatches(&modified(Attribute::Member))
+            .expect("Failed to determine webhook dispatches");
+        assert_eq!(dispatches.len(), 1);
+        assert_eq!(dispatches[0].webhook, webhook_uuid);
+        assert_eq!(dispatches[0].payload.target, group_uuid);
+        assert_eq!(dispatches[0].payload.action, WebhookAction::Modified);
+        assert!(dispatches[0].payload.classes.contains("group"));
+        // The secret was generated when the webhook was created.
+        assert!(!dispatches[0].secret.is_empty());
+
+        // Changes that don't match the filter are not sent.
+        let dispatches = idms_prox_read
+            .webhook_dispatches(&modified(Attribute::Description))
+            .expect("Failed to determine webhook dispatches");
+        assert!(dispatches.is_empty());
+
+        let created = AuditEvent::EntriesCreated {
+            source: AuditSource::Internal,
+            actor: UUID_ADMIN,
+            targets: BTreeSet::from([group_uuid]),
+            time,
+        };
+        let dispatches = idms_prox_read
+            .webhook_dispatches(&created)
+            .expect("Failed to determine webhook dispatches");
+        assert!(dispatches.is_empty());
+    }
+}
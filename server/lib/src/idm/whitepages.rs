@@ -0,0 +1,237 @@
+//! The white pages are a directory listing of the name, mail and phone of persons, for
+//! clients such as printers and address books that can't hold a credential. They are
+//! enabled by adding anonymous to `idm_white_pages_readers`.
+//!
+//! Anonymous searches of the white pages may be restricted to trusted networks, and rate
+//! limited per address, by settings on the domain. Outside of the trusted networks anonymous
+//! does not receive access to the white pages, and once the rate limit is reached searches
+//! are refused until the window has passed. The rate limit is held in memory on each server,
+//! and is not replicated.
+
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use concread::bptree::BptreeMap;
+
+use crate::idm::accountpolicy::TrustedNetwork;
+use crate::prelude::*;
+use crate::server::identity::Source;
+
+/// The window that the white pages rate limit applies over.
+pub const WHITE_PAGES_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// The domain settings that restrict anonymous access to the white pages.
+#[derive(Debug, Default)]
+pub(crate) struct WhitePagesPolicy {
+    /// If `None`, anonymous may search from any network.
+    trusted_networks: Option<Vec<TrustedNetwork>>,
+    /// The searches that may be made from one address in each window.
+    rate_limit: Option<u32>,
+}
+
+impl WhitePagesPolicy {
+    pub(crate) fn from_domain_entry(entry: &EntrySealedCommitted) -> Self {
+        // An invalid network is ignored, which can only make the policy stricter.
+        let trusted_networks = entry
+            .get_ava_set(Attribute::WhitePagesTrustedNetwork)
+            .and_then(|vs| vs.as_utf8_iter())
+            .map(|iter| {
+                iter.filter_map(|net| {
+                    TrustedNetwork::from_str(net)
+                        .map_err(|_| {
+                            warn!(?net, "Ignoring invalid white pages trusted network");
+                        })
+                        .ok()
+                })
+                .collect()
+            });
+
+        let rate_limit = entry.get_ava_single_uint32(Attribute::WhitePagesRateLimit);
+
+        WhitePagesPolicy {
+            trusted_networks,
+            rate_limit,
+        }
+    }
+
+    fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.trusted_networks
+            .as_ref()
+            .map_or(true, |nets| nets.iter().any(|net| net.contains(ip)))
+    }
+}
+
+/// If anonymous may search the white pages from a source.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum WhitePagesAccess {
+    Permitted,
+    Untrusted,
+    RateLimited,
+}
+
+#[derive(Debug, Clone)]
+struct WhitePagesWindow {
+    start: Duration,
+    count: u32,
+}
+
+/// Counts the anonymous searches of the white pages made from each address.
+pub struct WhitePagesLimiter {
+    windows: BptreeMap<IpAddr, WhitePagesWindow>,
+}
+
+impl Default for WhitePagesLimiter {
+    fn default() -> Self {
+        WhitePagesLimiter {
+            windows: BptreeMap::new(),
+        }
+    }
+}
+
+impl WhitePagesLimiter {
+    /// Check if anonymous may search the white pages from this source, recording the search
+    /// against the rate limit if it may.
+    pub(crate) fn check(
+        &self,
+        policy: &WhitePagesPolicy,
+        source: &Source,
+        ct: Duration,
+    ) -> WhitePagesAccess {
+        let ip = match source {
+            Source::Internal => return WhitePagesAccess::Permitted,
+            Source::Https(ip) | Source::Ldaps(ip) => ip.to_canonical(),
+        };
+
+        if !policy.is_trusted(&ip) {
+            return WhitePagesAccess::Untrusted;
+        }
+
+        let Some(rate_limit) = policy.rate_limit else {
+            return WhitePagesAccess::Permitted;
+        };
+
+        let mut windows_write = self.windows.write();
+
+        let mut window = windows_write
+            .get(&ip)
+            .filter(|window| ct < window.start + WHITE_PAGES_RATE_LIMIT_WINDOW)
+            .cloned()
+            .unwrap_or(WhitePagesWindow {
+                start: ct,
+                count: 0,
+            });
+
+        if window.count >= rate_limit {
+            return WhitePagesAccess::RateLimited;
+        }
+
+        window.count += 1;
+        windows_write.insert(ip, window);
+        windows_write.commit();
+
+        WhitePagesAccess::Permitted
+    }
+
+    /// Remove the windows that have passed.
+    pub(crate) fn expire(&self, ct: Duration) {
+        let mut windows_write = self.windows.write();
+        let expired: Vec<_> = windows_write
+            .iter()
+            .filter(|(_, window)| window.start + WHITE_PAGES_RATE_LIMIT_WINDOW <= ct)
+            .map(|(ip, _)| *ip)
+            .collect();
+        for ip in expired {
+            windows_write.remove(&ip);
+        }
+        windows_write.commit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(ip: &str) -> Source {
+        Source::Ldaps(IpAddr::from_str(ip).unwrap())
+    }
+
+    #[test]
+    fn test_white_pages_trusted_networks() {
+        let limiter = WhitePagesLimiter::default();
+        let ct = Duration::from_secs(1000);
+
+        let policy = WhitePagesPolicy::default();
+        assert_eq!(
+            limiter.check(&policy, &source("203.0.113.1"), ct),
+            WhitePagesAccess::Permitted
+        );
+
+        let policy = WhitePagesPolicy {
+            trusted_networks: Some(vec![TrustedNetwork::from_str("10.0.0.0/8").unwrap()]),
+            rate_limit: None,
+        };
+        assert_eq!(
+            limiter.check(&policy, &source("10.1.2.3"), ct),
+            WhitePagesAccess::Permitted
+        );
+        assert_eq!(
+            limiter.check(&policy, &source("::ffff:10.1.2.3"), ct),
+            WhitePagesAccess::Permitted
+        );
+        assert_eq!(
+            limiter.check(&policy, &source("203.0.113.1"), ct),
+            WhitePagesAccess::Untrusted
+        );
+
+        // If every network is invalid, no network is trusted.
+        let policy = WhitePagesPolicy {
+            trusted_networks: Some(Vec::with_capacity(0)),
+            rate_limit: None,
+        };
+        assert_eq!(
+            limiter.check(&policy, &source("10.1.2.3"), ct),
+            WhitePagesAccess::Untrusted
+        );
+        assert_eq!(
+            limiter.check(&policy, &Source::Internal, ct),
+            WhitePagesAccess::Permitted
+        );
+    }
+
+    #[test]
+    fn test_white_pages_rate_limit() {
+        let limiter = WhitePagesLimiter::default();
+        let ct = Duration::from_secs(1000);
+
+        let policy = WhitePagesPolicy {
+            trusted_networks: None,
+            rate_limit: Some(2),
+        };
+
+        for _ in 0..2 {
+            assert_eq!(
+                limiter.check(&policy, &source("10.1.2.3"), ct),
+                WhitePagesAccess::Permitted
+            );
+        }
+        assert_eq!(
+            limiter.check(&policy, &source("10.1.2.3"), ct),
+            WhitePagesAccess::RateLimited
+        );
+
+        // Each address is limited separately.
+        assert_eq!(
+            limiter.check(&policy, &source("10.1.2.4"), ct),
+            WhitePagesAccess::Permitted
+        );
+
+        // Once the window passes, searches are permitted again.
+        let ct = ct + WHITE_PAGES_RATE_LIMIT_WINDOW;
+        limiter.expire(ct);
+        assert_eq!(
+            limiter.check(&policy, &source("10.1.2.3"), ct),
+            WhitePagesAccess::Permitted
+        );
+    }
+}
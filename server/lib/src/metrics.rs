@@ -0,0 +1,388 @@
+//! Counters and timings of server activity for the metrics endpoint. These are recorded from
+//! many layers of the server (the query server, oauth2, ldap and replication), so they are owned
+//! by the backend and reached through its transactions. Each domain that this process serves has
+//! its own backend, so the metrics of one domain are never mixed with another. As with the
+//! authentication funnel, only totals are kept.
+
+use kanidm_proto::internal::Oauth2GrantType;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The grant that an OAuth2 token was issued by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenGrant {
+    AuthorizationCode,
+    ClientCredentials,
+    RefreshToken,
+    DeviceCode,
+    TokenExchange,
+}
+
+impl TokenGrant {
+    pub const ALL: [TokenGrant; 5] = [
+        TokenGrant::AuthorizationCode,
+        TokenGrant::ClientCredentials,
+        TokenGrant::RefreshToken,
+        TokenGrant::DeviceCode,
+        TokenGrant::TokenExchange,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TokenGrant::AuthorizationCode => "authorization_code",
+            TokenGrant::ClientCredentials => "client_credentials",
+            TokenGrant::RefreshToken => "refresh_token",
+            TokenGrant::DeviceCode => "device_code",
+            TokenGrant::TokenExchange => "token_exchange",
+        }
+    }
+}
+
+impl From<Oauth2GrantType> for TokenGrant {
+    fn from(grant_type: Oauth2GrantType) -> Self {
+        match grant_type {
+            Oauth2GrantType::AuthorizationCode => TokenGrant::AuthorizationCode,
+            Oauth2GrantType::ClientCredentials => TokenGrant::ClientCredentials,
+            Oauth2GrantType::RefreshToken => TokenGrant::RefreshToken,
+            Oauth2GrantType::DeviceCode => TokenGrant::DeviceCode,
+            Oauth2GrantType::TokenExchange => TokenGrant::TokenExchange,
+        }
+    }
+}
+
+/// An operation requested by an LDAP client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LdapOperation {
+    Bind,
+    Search,
+    Compare,
+    Whoami,
+    PasswordModify,
+    Unbind,
+}
+
+impl LdapOperation {
+    pub const ALL: [LdapOperation; 6] = [
+        LdapOperation::Bind,
+        LdapOperation::Search,
+        LdapOperation::Compare,
+        LdapOperation::Whoami,
+        LdapOperation::PasswordModify,
+        LdapOperation::Unbind,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LdapOperation::Bind => "bind",
+            LdapOperation::Search => "search",
+            LdapOperation::Compare => "compare",
+            LdapOperation::Whoami => "whoami",
+            LdapOperation::PasswordModify => "password_modify",
+            LdapOperation::Unbind => "unbind",
+        }
+    }
+}
+
+/// A cache of the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    Entry,
+    Idl,
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 2] = [CacheKind::Entry, CacheKind::Idl];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheKind::Entry => "entry",
+            CacheKind::Idl => "idl",
+        }
+    }
+}
+
+/// The upper bounds of the search latency histogram buckets, in microseconds.
+pub const SEARCH_LATENCY_BUCKETS_US: [u64; 10] = [
+    100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000, 5_000_000,
+];
+
+pub struct ServerMetrics {
+    tokens_issued: [AtomicU64; TokenGrant::ALL.len()],
+    ldap_operations: [AtomicU64; LdapOperation::ALL.len()],
+    // The final bucket counts searches slower than every bound.
+    search_buckets: [AtomicU64; SEARCH_LATENCY_BUCKETS_US.len() + 1],
+    search_sum_us: AtomicU64,
+    repl_lag_us: AtomicU64,
+    repl_last_success_secs: AtomicU64,
+    cache_hits: [AtomicU64; CacheKind::ALL.len()],
+    cache_misses: [AtomicU64; CacheKind::ALL.len()],
+    cache_evictions: [AtomicU64; CacheKind::ALL.len()],
+    cache_capacity: [AtomicU64; CacheKind::ALL.len()],
+    recycled_purged: AtomicU64,
+    tombstones_reaped: AtomicU64,
+    compaction_reclaimed_bytes: AtomicU64,
+    db_free_bytes: AtomicU64,
+}
+
+impl Default for ServerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerMetrics {
+    pub fn new() -> Self {
+        ServerMetrics {
+            tokens_issued: [const { AtomicU64::new(0) }; TokenGrant::ALL.len()],
+            ldap_operations: [const { AtomicU64::new(0) }; LdapOperation::ALL.len()],
+            search_buckets: [const { AtomicU64::new(0) }; SEARCH_LATENCY_BUCKETS_US.len() + 1],
+            search_sum_us: AtomicU64::new(0),
+            repl_lag_us: AtomicU64::new(0),
+            repl_last_success_secs: AtomicU64::new(0),
+            cache_hits: [const { AtomicU64::new(0) }; CacheKind::ALL.len()],
+            cache_misses: [const { AtomicU64::new(0) }; CacheKind::ALL.len()],
+            cache_evictions: [const { AtomicU64::new(0) }; CacheKind::ALL.len()],
+            cache_capacity: [const { AtomicU64::new(0) }; CacheKind::ALL.len()],
+            recycled_purged: AtomicU64::new(0),
+            tombstones_reaped: AtomicU64::new(0),
+            compaction_reclaimed_bytes: AtomicU64::new(0),
+            db_free_bytes: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn record_token_issued(&self, grant: TokenGrant) {
+        self.tokens_issued[grant as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_ldap_operation(&self, op: LdapOperation) {
+        self.ldap_operations[op as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_search(&self, elapsed: Duration) {
+        let us = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        let bucket = SEARCH_LATENCY_BUCKETS_US
+            .iter()
+            .position(|bound| us <= *bound)
+            .unwrap_or(SEARCH_LATENCY_BUCKETS_US.len());
+        self.search_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.search_sum_us.fetch_add(us, Ordering::Relaxed);
+    }
+
+    /// Record a successful replication from a supplier at `ct`, where `lag` is how long ago the
+    /// newest change we received was made.
+    pub(crate) fn record_replication(&self, ct: Duration, lag: Duration) {
+        let lag_us = u64::try_from(lag.as_micros()).unwrap_or(u64::MAX);
+        self.repl_lag_us.store(lag_us, Ordering::Relaxed);
+        self.repl_last_success_secs
+            .store(ct.as_secs(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_cache_lookups(&self, cache: CacheKind, hits: u64, misses: u64) {
+        self.cache_hits[cache as usize].fetch_add(hits, Ordering::Relaxed);
+        self.cache_misses[cache as usize].fetch_add(misses, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_cache_evictions(&self, cache: CacheKind, evictions: u64) {
+        self.cache_evictions[cache as usize].fetch_add(evictions, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_cache_capacity(&self, cache: CacheKind, capacity: usize) {
+        self.cache_capacity[cache as usize].store(capacity as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_recycled_purged(&self, count: usize) {
+        self.recycled_purged
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_tombstones_reaped(&self, count: usize) {
+        self.tombstones_reaped
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_compaction(&self, reclaimed_bytes: u64, free_bytes: u64) {
+        self.compaction_reclaimed_bytes
+            .fetch_add(reclaimed_bytes, Ordering::Relaxed);
+        self.db_free_bytes.store(free_bytes, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ServerMetricsSnapshot {
+        let mut cumulative = 0;
+        let search_buckets = self.search_buckets.each_ref().map(|c| {
+            cumulative += c.load(Ordering::Relaxed);
+            cumulative
+        });
+
+        let repl_last_success_secs = self.repl_last_success_secs.load(Ordering::Relaxed);
+
+        ServerMetricsSnapshot {
+            tokens_issued: self
+                .tokens_issued
+                .each_ref()
+                .map(|c| c.load(Ordering::Relaxed)),
+            ldap_operations: self
+                .ldap_operations
+                .each_ref()
+                .map(|c| c.load(Ordering::Relaxed)),
+            search_buckets,
+            search_sum: Duration::from_micros(self.search_sum_us.load(Ordering::Relaxed)),
+            replication: (repl_last_success_secs != 0).then(|| ReplicationMetrics {
+                lag: Duration::from_micros(self.repl_lag_us.load(Ordering::Relaxed)),
+                last_success: Duration::from_secs(repl_last_success_secs),
+            }),
+            cache_hits: self
+                .cache_hits
+                .each_ref()
+                .map(|c| c.load(Ordering::Relaxed)),
+            cache_misses: self
+                .cache_misses
+                .each_ref()
+                .map(|c| c.load(Ordering::Relaxed)),
+            cache_evictions: self
+                .cache_evictions
+                .each_ref()
+                .map(|c| c.load(Ordering::Relaxed)),
+            cache_capacity: self
+                .cache_capacity
+                .each_ref()
+                .map(|c| c.load(Ordering::Relaxed)),
+            recycled_purged: self.recycled_purged.load(Ordering::Relaxed),
+            tombstones_reaped: self.tombstones_reaped.load(Ordering::Relaxed),
+            compaction_reclaimed_bytes: self.compaction_reclaimed_bytes.load(Ordering::Relaxed),
+            db_free_bytes: self.db_free_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicationMetrics {
+    /// How far behind its supplier this server was at the last successful replication.
+    pub lag: Duration,
+    /// When the last successful replication occurred, since the unix epoch.
+    pub last_success: Duration,
+}
+
+/// The metrics of the server at a point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerMetricsSnapshot {
+    tokens_issued: [u64; TokenGrant::ALL.len()],
+    ldap_operations: [u64; LdapOperation::ALL.len()],
+    search_buckets: [u64; SEARCH_LATENCY_BUCKETS_US.len() + 1],
+    /// The total time spent processing searches.
+    pub search_sum: Duration,
+    /// This is `None` until this server has replicated from a supplier.
+    pub replication: Option<ReplicationMetrics>,
+    cache_hits: [u64; CacheKind::ALL.len()],
+    cache_misses: [u64; CacheKind::ALL.len()],
+    cache_evictions: [u64; CacheKind::ALL.len()],
+    cache_capacity: [u64; CacheKind::ALL.len()],
+    /// The number of recycled entries that were purged to tombstones.
+    pub recycled_purged: u64,
+    /// The number of tombstones that were removed after leaving the changelog.
+    pub tombstones_reaped: u64,
+    /// The bytes returned to the filesystem by compaction after purges.
+    pub compaction_reclaimed_bytes: u64,
+    /// The bytes free within the database after the last compaction.
+    pub db_free_bytes: u64,
+}
+
+impl ServerMetricsSnapshot {
+    pub fn tokens_issued(&self, grant: TokenGrant) -> u64 {
+        self.tokens_issued[grant as usize]
+    }
+
+    pub fn ldap_operations(&self, op: LdapOperation) -> u64 {
+        self.ldap_operations[op as usize]
+    }
+
+    /// The number of searches that completed within each bound of
+    /// [`SEARCH_LATENCY_BUCKETS_US`]. Like a prometheus histogram, these are cumulative.
+    pub fn search_buckets(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        SEARCH_LATENCY_BUCKETS_US
+            .iter()
+            .copied()
+            .zip(self.search_buckets.iter().copied())
+    }
+
+    /// The total number of searches.
+    pub fn search_count(&self) -> u64 {
+        self.search_buckets.last().copied().unwrap_or_default()
+    }
+
+    pub fn cache_hits(&self, cache: CacheKind) -> u64 {
+        self.cache_hits[cache as usize]
+    }
+
+    pub fn cache_misses(&self, cache: CacheKind) -> u64 {
+        self.cache_misses[cache as usize]
+    }
+
+    /// The number of items evicted from the cache to make room for others.
+    pub fn cache_evictions(&self, cache: CacheKind) -> u64 {
+        self.cache_evictions[cache as usize]
+    }
+
+    /// The number of items the cache was sized to hold when the server started.
+    pub fn cache_capacity(&self, cache: CacheKind) -> u64 {
+        self.cache_capacity[cache as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheKind, LdapOperation, ServerMetrics, TokenGrant};
+    use std::time::Duration;
+
+    #[test]
+    fn test_server_metrics() {
+        let metrics = ServerMetrics::new();
+
+        metrics.record_token_issued(TokenGrant::RefreshToken);
+        metrics.record_token_issued(TokenGrant::RefreshToken);
+        metrics.record_ldap_operation(LdapOperation::Bind);
+        metrics.record_search(Duration::from_micros(50));
+        metrics.record_search(Duration::from_millis(3));
+        metrics.record_search(Duration::from_secs(60));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.tokens_issued(TokenGrant::RefreshToken), 2);
+        assert_eq!(snapshot.tokens_issued(TokenGrant::AuthorizationCode), 0);
+        assert_eq!(snapshot.ldap_operations(LdapOperation::Bind), 1);
+        assert_eq!(snapshot.ldap_operations(LdapOperation::Search), 0);
+
+        let buckets: Vec<_> = snapshot.search_buckets().collect();
+        assert_eq!(buckets[0], (100, 1));
+        assert_eq!(buckets[2], (1_000, 1));
+        assert_eq!(buckets[3], (5_000, 2));
+        assert_eq!(buckets[9], (5_000_000, 2));
+        assert_eq!(snapshot.search_count(), 3);
+        assert!(snapshot.replication.is_none());
+
+        metrics.record_replication(Duration::from_secs(100), Duration::from_secs(2));
+        let snapshot = metrics.snapshot();
+        let repl = snapshot.replication.expect("replication metrics missing");
+        assert_eq!(repl.lag, Duration::from_secs(2));
+        assert_eq!(repl.last_success, Duration::from_secs(100));
+
+        metrics.set_cache_capacity(CacheKind::Entry, 2048);
+        metrics.record_cache_lookups(CacheKind::Entry, 3, 1);
+        metrics.record_cache_evictions(CacheKind::Entry, 2);
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.cache_capacity(CacheKind::Entry), 2048);
+        assert_eq!(snapshot.cache_hits(CacheKind::Entry), 3);
+        assert_eq!(snapshot.cache_misses(CacheKind::Entry), 1);
+        assert_eq!(snapshot.cache_evictions(CacheKind::Entry), 2);
+        assert_eq!(snapshot.cache_hits(CacheKind::Idl), 0);
+
+        metrics.record_recycled_purged(4);
+        metrics.record_tombstones_reaped(2);
+        metrics.record_tombstones_reaped(1);
+        metrics.record_compaction(8192, 4096);
+        metrics.record_compaction(0, 0);
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.recycled_purged, 4);
+        assert_eq!(snapshot.tombstones_reaped, 3);
+        assert_eq!(snapshot.compaction_reclaimed_bytes, 8192);
+        assert_eq!(snapshot.db_free_bytes, 0);
+    }
+}
@@ -360,8 +360,10 @@ lazy_static! {
             Attribute::Name,
             Attribute::Description,
             Attribute::AcpEnable,
+            Attribute::AcpStaged,
             Attribute::AcpReceiverGroup,
             Attribute::AcpTargetScope,
+            Attribute::AcpTargetGroup,
             Attribute::AcpSearchAttr,
             Attribute::AcpModifyRemovedAttr,
             Attribute::AcpModifyPresentAttr,
@@ -374,8 +376,10 @@ lazy_static! {
             Attribute::Name,
             Attribute::Description,
             Attribute::AcpEnable,
+            Attribute::AcpStaged,
             Attribute::AcpReceiverGroup,
             Attribute::AcpTargetScope,
+            Attribute::AcpTargetGroup,
             Attribute::AcpSearchAttr,
             Attribute::AcpModifyRemovedAttr,
             Attribute::AcpModifyPresentAttr,
@@ -388,8 +392,10 @@ lazy_static! {
             Attribute::Name,
             Attribute::Description,
             Attribute::AcpEnable,
+            Attribute::AcpStaged,
             Attribute::AcpReceiverGroup,
             Attribute::AcpTargetScope,
+            Attribute::AcpTargetGroup,
             Attribute::AcpSearchAttr,
             Attribute::AcpModifyRemovedAttr,
             Attribute::AcpModifyPresentAttr,
@@ -402,8 +408,10 @@ lazy_static! {
             Attribute::Name,
             Attribute::Description,
             Attribute::AcpEnable,
+            Attribute::AcpStaged,
             Attribute::AcpReceiverGroup,
             Attribute::AcpTargetScope,
+            Attribute::AcpTargetGroup,
             Attribute::AcpSearchAttr,
             Attribute::AcpModifyRemovedAttr,
             Attribute::AcpModifyPresentAttr,
@@ -417,6 +425,9 @@ lazy_static! {
             EntryClass::AccessControlModify,
             EntryClass::AccessControlCreate,
             EntryClass::AccessControlDelete,
+            EntryClass::AccessControlReceiverGroup,
+            EntryClass::AccessControlTargetScope,
+            EntryClass::AccessControlTargetGroup,
         ],
         create_classes: vec![
             EntryClass::AccessControlProfile,
@@ -424,6 +435,9 @@ lazy_static! {
             EntryClass::AccessControlModify,
             EntryClass::AccessControlCreate,
             EntryClass::AccessControlDelete,
+            EntryClass::AccessControlReceiverGroup,
+            EntryClass::AccessControlTargetScope,
+            EntryClass::AccessControlTargetGroup,
         ],
     };
 }
@@ -456,6 +470,7 @@ lazy_static! {
             Attribute::Spn,
             Attribute::Description,
             Attribute::Member,
+            Attribute::ParentGroup,
             Attribute::EntryManagedBy,
         ],
         ..Default::default()
@@ -572,6 +587,27 @@ lazy_static! {
             Attribute::LimitSearchMaxResults,
             Attribute::LimitSearchMaxFilterTest,
             Attribute::AllowPrimaryCredFallback,
+            Attribute::AuthTrustedNetwork,
+            Attribute::CredentialTypeMinimumUntrustedNetwork,
+            Attribute::AuthLockoutThreshold,
+            Attribute::AuthLockoutWindow,
+            Attribute::AuthLockoutDuration,
+            Attribute::AuthPasswordHistoryCount,
+            Attribute::AllowUnixPassword,
+            Attribute::AllowApiTokens,
+            Attribute::AllowEmailLink,
+            Attribute::PasswordBreachCheck,
+            Attribute::AuthPasswordMaximumAge,
+            Attribute::AuthSessionIdleTimeout,
+            Attribute::LoginHostTag,
+            Attribute::SshKeyAllowedType,
+            Attribute::SshKeyRsaMinimumBits,
+            Attribute::PosixDefaultShell,
+            Attribute::PosixHomeTemplate,
+            Attribute::PosixGecosFormat,
+            Attribute::PosixHomeSkeleton,
+            Attribute::AccountLifetime,
+            Attribute::AccountArchiveDelay,
         ],
         modify_removed_attrs: vec![
             Attribute::Class,
@@ -583,6 +619,27 @@ lazy_static! {
             Attribute::LimitSearchMaxResults,
             Attribute::LimitSearchMaxFilterTest,
             Attribute::AllowPrimaryCredFallback,
+            Attribute::AuthTrustedNetwork,
+            Attribute::CredentialTypeMinimumUntrustedNetwork,
+            Attribute::AuthLockoutThreshold,
+            Attribute::AuthLockoutWindow,
+            Attribute::AuthLockoutDuration,
+            Attribute::AuthPasswordHistoryCount,
+            Attribute::AllowUnixPassword,
+            Attribute::AllowApiTokens,
+            Attribute::AllowEmailLink,
+            Attribute::PasswordBreachCheck,
+            Attribute::AuthPasswordMaximumAge,
+            Attribute::AuthSessionIdleTimeout,
+            Attribute::LoginHostTag,
+            Attribute::SshKeyAllowedType,
+            Attribute::SshKeyRsaMinimumBits,
+            Attribute::PosixDefaultShell,
+            Attribute::PosixHomeTemplate,
+            Attribute::PosixGecosFormat,
+            Attribute::PosixHomeSkeleton,
+            Attribute::AccountLifetime,
+            Attribute::AccountArchiveDelay,
         ],
         modify_present_attrs: vec![
             Attribute::Class,
@@ -594,6 +651,27 @@ lazy_static! {
             Attribute::LimitSearchMaxResults,
             Attribute::LimitSearchMaxFilterTest,
             Attribute::AllowPrimaryCredFallback,
+            Attribute::AuthTrustedNetwork,
+            Attribute::CredentialTypeMinimumUntrustedNetwork,
+            Attribute::AuthLockoutThreshold,
+            Attribute::AuthLockoutWindow,
+            Attribute::AuthLockoutDuration,
+            Attribute::AuthPasswordHistoryCount,
+            Attribute::AllowUnixPassword,
+            Attribute::AllowApiTokens,
+            Attribute::AllowEmailLink,
+            Attribute::PasswordBreachCheck,
+            Attribute::AuthPasswordMaximumAge,
+            Attribute::AuthSessionIdleTimeout,
+            Attribute::LoginHostTag,
+            Attribute::SshKeyAllowedType,
+            Attribute::SshKeyRsaMinimumBits,
+            Attribute::PosixDefaultShell,
+            Attribute::PosixHomeTemplate,
+            Attribute::PosixGecosFormat,
+            Attribute::PosixHomeSkeleton,
+            Attribute::AccountLifetime,
+            Attribute::AccountArchiveDelay,
         ],
         modify_classes: vec![EntryClass::AccountPolicy,],
         ..Default::default()
@@ -1167,8 +1245,14 @@ lazy_static! {
             Attribute::DomainUuid,
             Attribute::KeyInternalData,
             Attribute::LdapAllowUnixPwBind,
+            Attribute::LdapGroupCompat,
             Attribute::Version,
             Attribute::Image,
+            Attribute::DomainTheme,
+            Attribute::DomainLoginBanner,
+            Attribute::DomainPrimaryColour,
+            Attribute::WhitePagesTrustedNetwork,
+            Attribute::WhitePagesRateLimit,
         ],
         modify_removed_attrs: vec![
             Attribute::DomainDisplayName,
@@ -1177,9 +1261,15 @@ lazy_static! {
             Attribute::LdapMaxQueryableAttrs,
             Attribute::DomainAllowEasterEggs,
             Attribute::LdapAllowUnixPwBind,
+            Attribute::LdapGroupCompat,
             Attribute::KeyActionRevoke,
             Attribute::KeyActionRotate,
             Attribute::Image,
+            Attribute::DomainTheme,
+            Attribute::DomainLoginBanner,
+            Attribute::DomainPrimaryColour,
+            Attribute::WhitePagesTrustedNetwork,
+            Attribute::WhitePagesRateLimit,
         ],
         modify_present_attrs: vec![
             Attribute::DomainDisplayName,
@@ -1188,9 +1278,15 @@ lazy_static! {
             Attribute::DomainSsid,
             Attribute::DomainAllowEasterEggs,
             Attribute::LdapAllowUnixPwBind,
+            Attribute::LdapGroupCompat,
             Attribute::KeyActionRevoke,
             Attribute::KeyActionRotate,
             Attribute::Image,
+            Attribute::DomainTheme,
+            Attribute::DomainLoginBanner,
+            Attribute::DomainPrimaryColour,
+            Attribute::WhitePagesTrustedNetwork,
+            Attribute::WhitePagesRateLimit,
         ],
         ..Default::default()
     };
@@ -1227,6 +1323,7 @@ lazy_static! {
             Attribute::SyncCredentialPortal,
             Attribute::SyncYieldAuthority,
             Attribute::SyncCookie,
+            Attribute::SyncConflict,
         ],
         modify_removed_attrs: vec![
             Attribute::Name,
@@ -1236,6 +1333,7 @@ lazy_static! {
             Attribute::SyncCredentialPortal,
             Attribute::SyncCookie,
             Attribute::SyncYieldAuthority,
+            Attribute::SyncConflict,
         ],
         modify_present_attrs: vec![
             Attribute::Name,
@@ -1275,16 +1373,22 @@ lazy_static! {
             Attribute::Uuid,
             Attribute::Description,
             Attribute::Member,
+            Attribute::MemberValidUntil,
+            Attribute::MembershipRequestable,
             Attribute::DynMember,
             Attribute::EntryManagedBy,
         ],
         modify_present_attrs: vec![
             Attribute::Description,
             Attribute::Member,
+            Attribute::MemberValidUntil,
+            Attribute::MembershipRequestable,
         ],
         modify_removed_attrs: vec![
             Attribute::Description,
             Attribute::Member,
+            Attribute::MemberValidUntil,
+            Attribute::MembershipRequestable,
         ],
         ..Default::default()
     };
@@ -1476,6 +1580,54 @@ lazy_static! {
     };
 }
 
+lazy_static! {
+    pub static ref IDM_ACP_SELF_READ_DL10: BuiltinAcp = BuiltinAcp {
+        name: "idm_acp_self_read",
+        uuid: UUID_IDM_ACP_SELF_READ,
+        description:
+            "Builtin IDM Control for self read - required for whoami and many other functions",
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlSearch,
+        ],
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_ALL_ACCOUNTS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::SelfUuid),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Name,
+            Attribute::Spn,
+            Attribute::DisplayName,
+            Attribute::LegalName,
+            Attribute::Class,
+            Attribute::MemberOf,
+            Attribute::Mail,
+            Attribute::Phone,
+            Attribute::RadiusSecret,
+            Attribute::GidNumber,
+            Attribute::LoginShell,
+            Attribute::PosixHomeQuota,
+            Attribute::Uuid,
+            Attribute::SyncParentUuid,
+            Attribute::AccountExpire,
+            Attribute::AccountValidFrom,
+            Attribute::PrimaryCredential,
+            Attribute::UserAuthTokenSession,
+            Attribute::OAuth2Session,
+            Attribute::PassKeys,
+            Attribute::AttestedPasskeys,
+            Attribute::CredentialUsage,
+            Attribute::ApplicationPassword,
+            Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
+            Attribute::UnixPassword,
+            Attribute::NotificationOptOut,
+            Attribute::UiTheme,
+        ],
+        ..Default::default()
+    };
+}
+
 lazy_static! {
     pub static ref IDM_ACP_SELF_WRITE_V1: BuiltinAcp = BuiltinAcp{
         name: "idm_acp_self_write",
@@ -1531,6 +1683,7 @@ lazy_static! {
             Attribute::RadiusSecret,
             Attribute::PrimaryCredential,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
             Attribute::UnixPassword,
             Attribute::PassKeys,
             Attribute::AttestedPasskeys,
@@ -1540,6 +1693,7 @@ lazy_static! {
             Attribute::RadiusSecret,
             Attribute::PrimaryCredential,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
             Attribute::UnixPassword,
             Attribute::PassKeys,
             Attribute::AttestedPasskeys,
@@ -1583,6 +1737,47 @@ lazy_static! {
     };
 }
 
+lazy_static! {
+    pub static ref IDM_ACP_SELF_WRITE_DL10: BuiltinAcp = BuiltinAcp{
+        name: "idm_acp_self_write",
+        uuid: UUID_IDM_ACP_SELF_WRITE_V1,
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlModify,
+            ],
+        description: "Builtin IDM Control for self write - required for people to update their own credentials in line with best practices.",
+        receiver: BuiltinAcpReceiver::Group ( vec![UUID_IDM_ALL_PERSONS] ),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::SelfUuid),
+        modify_removed_attrs: vec![
+            Attribute::RadiusSecret,
+            Attribute::PrimaryCredential,
+            Attribute::SshPublicKey,
+            Attribute::UnixPassword,
+            Attribute::PassKeys,
+            Attribute::AttestedPasskeys,
+            Attribute::UserAuthTokenSession,
+            Attribute::ApplicationPassword,
+            Attribute::NotificationOptOut,
+            Attribute::UiTheme,
+            Attribute::Phone,
+        ],
+        modify_present_attrs: vec![
+            Attribute::RadiusSecret,
+            Attribute::PrimaryCredential,
+            Attribute::SshPublicKey,
+            Attribute::UnixPassword,
+            Attribute::PassKeys,
+            Attribute::AttestedPasskeys,
+            Attribute::ApplicationPassword,
+            Attribute::NotificationOptOut,
+            Attribute::UiTheme,
+            Attribute::Phone,
+        ],
+        ..Default::default()
+    };
+}
+
 lazy_static! {
     pub static ref IDM_ACP_SELF_NAME_WRITE_V1: BuiltinAcp = BuiltinAcp{
         name: "idm_acp_self_name_write",
@@ -1688,6 +1883,7 @@ lazy_static! {
             Attribute::GidNumber,
             Attribute::LoginShell,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
         ],
         ..Default::default()
     };
@@ -1742,6 +1938,7 @@ lazy_static! {
             Attribute::Description,
             Attribute::BadlistPassword,
             Attribute::DeniedName,
+            Attribute::DeniedPasswordTerm,
             Attribute::AuthSessionExpiry,
             Attribute::PrivilegeExpiry,
             Attribute::Version,
@@ -1749,12 +1946,14 @@ lazy_static! {
         modify_removed_attrs: vec![
             Attribute::BadlistPassword,
             Attribute::DeniedName,
+            Attribute::DeniedPasswordTerm,
             Attribute::AuthSessionExpiry,
             Attribute::PrivilegeExpiry,
         ],
         modify_present_attrs: vec![
             Attribute::BadlistPassword,
             Attribute::DeniedName,
+            Attribute::DeniedPasswordTerm,
             Attribute::AuthSessionExpiry,
             Attribute::PrivilegeExpiry,
         ],
@@ -1892,6 +2091,9 @@ lazy_static! {
             Attribute::Description,
             Attribute::Mail,
             Attribute::Member,
+            Attribute::MemberValidUntil,
+            Attribute::MembershipRequestable,
+            Attribute::ParentGroup,
             Attribute::DynMember,
             Attribute::EntryManagedBy,
         ],
@@ -1902,6 +2104,8 @@ lazy_static! {
             Attribute::Description,
             Attribute::Mail,
             Attribute::Member,
+            Attribute::MembershipRequestable,
+            Attribute::ParentGroup,
             Attribute::EntryManagedBy,
         ],
         create_classes: vec![
@@ -1913,6 +2117,9 @@ lazy_static! {
             Attribute::Description,
             Attribute::Mail,
             Attribute::Member,
+            Attribute::MemberValidUntil,
+            Attribute::MembershipRequestable,
+            Attribute::ParentGroup,
             Attribute::EntryManagedBy,
         ],
         modify_removed_attrs: vec![
@@ -1920,6 +2127,9 @@ lazy_static! {
             Attribute::Description,
             Attribute::Mail,
             Attribute::Member,
+            Attribute::MemberValidUntil,
+            Attribute::MembershipRequestable,
+            Attribute::ParentGroup,
             Attribute::EntryManagedBy,
         ],
         ..Default::default()
@@ -1983,21 +2193,27 @@ lazy_static! {
             Attribute::Description,
             Attribute::GidNumber,
             Attribute::LoginShell,
+            Attribute::PosixHomeQuota,
             Attribute::UnixPassword,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
         ],
         modify_removed_attrs: vec![
             Attribute::GidNumber,
             Attribute::LoginShell,
+            Attribute::PosixHomeQuota,
             Attribute::UnixPassword,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
         ],
         modify_present_attrs: vec![
             Attribute::Class,
             Attribute::GidNumber,
             Attribute::LoginShell,
+            Attribute::PosixHomeQuota,
             Attribute::UnixPassword,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
         ],
         modify_classes: vec![EntryClass::PosixAccount,],
         ..Default::default()
@@ -2027,6 +2243,7 @@ lazy_static! {
             Attribute::DisplayName,
             Attribute::LegalName,
             Attribute::Mail,
+            Attribute::Phone,
         ],
         ..Default::default()
     };
@@ -2052,12 +2269,14 @@ lazy_static! {
             Attribute::DisplayName,
             Attribute::LegalName,
             Attribute::Mail,
+            Attribute::Phone,
         ],
         modify_present_attrs: vec![
             Attribute::Name,
             Attribute::DisplayName,
             Attribute::LegalName,
             Attribute::Mail,
+            Attribute::Phone,
         ],
         ..Default::default()
     };
@@ -2094,6 +2313,43 @@ lazy_static! {
         create_classes: vec![EntryClass::Object, EntryClass::Account, EntryClass::Person,],
         ..Default::default()
     };
+
+    pub static ref IDM_ACP_PEOPLE_CREATE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+        ],
+        name: "idm_acp_people_create",
+        uuid: UUID_IDM_ACP_PEOPLE_CREATE_V1,
+        description: "Builtin IDM Control for creating new persons.",
+        receiver: BuiltinAcpReceiver::Group(vec![
+            UUID_IDM_PEOPLE_ADMINS,
+            UUID_IDM_PEOPLE_ON_BOARDING
+        ]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Person).clone(),
+            match_class_filter!(EntryClass::Account).clone(),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Mail,
+            Attribute::AccountExpire,
+            Attribute::AccountValidFrom,
+            Attribute::Sponsor,
+        ],
+        create_classes: vec![
+            EntryClass::Object,
+            EntryClass::Account,
+            EntryClass::Person,
+            EntryClass::Contractor,
+        ],
+        ..Default::default()
+    };
 }
 
 lazy_static! {
@@ -2116,6 +2372,37 @@ lazy_static! {
         modify_present_attrs: vec![Attribute::AccountExpire, Attribute::AccountValidFrom,],
         ..Default::default()
     };
+
+    pub static ref IDM_ACP_PEOPLE_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlModify,
+        ],
+        name: "idm_acp_people_manage",
+        uuid: UUID_IDM_ACP_PEOPLE_MANAGE_V1,
+        description: "Builtin IDM Control for management of peoples non sensitive attributes.",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_PEOPLE_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Person),
+            match_class_filter!(EntryClass::Account),
+            FILTER_ANDNOT_HP_OR_RECYCLED_OR_TOMBSTONE.clone(),
+        ])),
+        modify_removed_attrs: vec![
+            Attribute::Class,
+            Attribute::AccountExpire,
+            Attribute::AccountValidFrom,
+            Attribute::Sponsor,
+        ],
+        modify_present_attrs: vec![
+            Attribute::Class,
+            Attribute::AccountExpire,
+            Attribute::AccountValidFrom,
+            Attribute::Sponsor,
+        ],
+        modify_classes: vec![EntryClass::Contractor],
+        ..Default::default()
+    };
 }
 
 // Person Read
@@ -2152,6 +2439,40 @@ lazy_static! {
         ],
         ..Default::default()
     };
+
+    pub static ref IDM_ACP_PEOPLE_READ_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlSearch,
+        ],
+        name: "idm_acp_people_read",
+        uuid: UUID_IDM_ACP_PEOPLE_READ_V1,
+        description: "Builtin IDM Control for reading non-sensitive data.",
+        receiver: BuiltinAcpReceiver::Group(vec![
+            UUID_IDM_PEOPLE_ADMINS,
+            UUID_IDM_PEOPLE_PII_READ,
+            UUID_IDM_ACCOUNT_MAIL_READ,
+            UUID_IDM_SERVICE_DESK
+        ]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Person).clone(),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Name,
+            Attribute::Spn,
+            Attribute::Uuid,
+            Attribute::DisplayName,
+            Attribute::MemberOf,
+            Attribute::Uuid,
+            Attribute::AccountExpire,
+            Attribute::AccountValidFrom,
+            Attribute::Sponsor,
+        ],
+        ..Default::default()
+    };
 }
 
 // Person Delete
@@ -2222,6 +2543,54 @@ lazy_static! {
     };
 }
 
+lazy_static! {
+    pub static ref IDM_ACP_PEOPLE_CREDENTIAL_RESET_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_people_credential_reset",
+        uuid: UUID_IDM_ACP_PEOPLE_CREDENTIAL_RESET_V1,
+        description: "Builtin IDM Control for resetting peoples credentials ",
+        receiver: BuiltinAcpReceiver::Group(vec![
+            UUID_IDM_PEOPLE_ADMINS,
+            UUID_IDM_SERVICE_DESK,
+            UUID_IDM_PEOPLE_ON_BOARDING,
+        ]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Person),
+            match_class_filter!(EntryClass::Account),
+            FILTER_ANDNOT_HP_OR_RECYCLED_OR_TOMBSTONE.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Spn,
+            Attribute::PrimaryCredential,
+            Attribute::AccountExpire,
+            Attribute::AccountValidFrom,
+            Attribute::PassKeys,
+            Attribute::AttestedPasskeys,
+            Attribute::CredentialUsage,
+        ],
+        modify_removed_attrs: vec![
+            Attribute::PrimaryCredential,
+            Attribute::PassKeys,
+            Attribute::AttestedPasskeys,
+        ],
+        modify_present_attrs: vec![
+            Attribute::PrimaryCredential,
+            Attribute::PassKeys,
+            Attribute::AttestedPasskeys,
+            Attribute::PasswordImport,
+        ],
+        ..Default::default()
+    };
+}
+
 // HP Person Account Credential Reset
 lazy_static! {
     pub static ref IDM_ACP_HP_PEOPLE_CREDENTIAL_RESET_V1: BuiltinAcp = BuiltinAcp {
@@ -2270,43 +2639,92 @@ lazy_static! {
     };
 }
 
-// Service Account Create/Manage
-//   needs to be able to assign to entry managed by
 lazy_static! {
-    pub static ref IDM_ACP_SERVICE_ACCOUNT_CREATE_V1: BuiltinAcp = BuiltinAcp {
+    pub static ref IDM_ACP_HP_PEOPLE_CREDENTIAL_RESET_DL10: BuiltinAcp = BuiltinAcp {
         classes: vec![
             EntryClass::Object,
             EntryClass::AccessControlProfile,
-            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
         ],
-        name: "idm_acp_service_account_create",
-        uuid: UUID_IDM_ACP_SERVICE_ACCOUNT_CREATE_V1,
-        description: "Builtin IDM Control for creating new service accounts.",
-        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_SERVICE_ACCOUNT_ADMINS]),
+        name: "idm_acp_hp_people_credential_reset",
+        uuid: UUID_IDM_ACP_HP_PEOPLE_CREDENTIAL_RESET_V1,
+        description: "Builtin IDM Control for resetting high privilege peoples credentials ",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_PEOPLE_ADMINS,]),
         target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
-            match_class_filter!(EntryClass::ServiceAccount).clone(),
-            match_class_filter!(EntryClass::Account).clone(),
+            match_class_filter!(EntryClass::Person),
+            match_class_filter!(EntryClass::Account),
+            FILTER_HP.clone(),
             FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
         ])),
-        create_attrs: vec![
+        search_attrs: vec![
             Attribute::Class,
+            Attribute::Uuid,
             Attribute::Name,
-            Attribute::DisplayName,
-            Attribute::EntryManagedBy,
-            Attribute::Description,
+            Attribute::Spn,
+            Attribute::PrimaryCredential,
             Attribute::AccountExpire,
             Attribute::AccountValidFrom,
+            Attribute::PassKeys,
+            Attribute::AttestedPasskeys,
+            Attribute::CredentialUsage,
         ],
-        create_classes: vec![
-            EntryClass::Object,
-            EntryClass::Account,
-            EntryClass::ServiceAccount,
+        modify_removed_attrs: vec![
+            Attribute::PrimaryCredential,
+            Attribute::AccountExpire,
+            Attribute::AccountValidFrom,
+            Attribute::PassKeys,
+            Attribute::AttestedPasskeys,
         ],
-        ..Default::default()
-    };
-}
-
-lazy_static! {
+        modify_present_attrs: vec![
+            Attribute::PrimaryCredential,
+            Attribute::AccountExpire,
+            Attribute::AccountValidFrom,
+            Attribute::PassKeys,
+            Attribute::AttestedPasskeys,
+            Attribute::PasswordImport,
+        ],
+        ..Default::default()
+    };
+}
+
+// Service Account Create/Manage
+//   needs to be able to assign to entry managed by
+lazy_static! {
+    pub static ref IDM_ACP_SERVICE_ACCOUNT_CREATE_V1: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+        ],
+        name: "idm_acp_service_account_create",
+        uuid: UUID_IDM_ACP_SERVICE_ACCOUNT_CREATE_V1,
+        description: "Builtin IDM Control for creating new service accounts.",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_SERVICE_ACCOUNT_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::ServiceAccount).clone(),
+            match_class_filter!(EntryClass::Account).clone(),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::EntryManagedBy,
+            Attribute::Description,
+            Attribute::AccountExpire,
+            Attribute::AccountValidFrom,
+        ],
+        create_classes: vec![
+            EntryClass::Object,
+            EntryClass::Account,
+            EntryClass::ServiceAccount,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
     pub static ref IDM_ACP_SERVICE_ACCOUNT_MANAGE_V1: BuiltinAcp = BuiltinAcp {
         classes: vec![
             EntryClass::Object,
@@ -2327,10 +2745,12 @@ lazy_static! {
             Attribute::DisplayName,
             Attribute::Mail,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
             Attribute::UnixPassword,
             Attribute::PrimaryCredential,
             Attribute::ApiTokenSession,
             Attribute::UserAuthTokenSession,
+            Attribute::JwtPublicKey,
         ],
         modify_present_attrs: vec![Attribute::Name, Attribute::DisplayName, Attribute::Mail,],
         ..Default::default()
@@ -2385,6 +2805,7 @@ lazy_static! {
             Attribute::EntryManagedBy,
             Attribute::DisplayName,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
             Attribute::GidNumber,
             Attribute::LoginShell,
             Attribute::UnixPassword,
@@ -2394,10 +2815,12 @@ lazy_static! {
             Attribute::AccountValidFrom,
             Attribute::ApiTokenSession,
             Attribute::UserAuthTokenSession,
+            Attribute::JwtPublicKey,
         ],
         modify_removed_attrs: vec![
             Attribute::DisplayName,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
             Attribute::PrimaryCredential,
             Attribute::UnixPassword,
             // For legacy upgrades we allow removing this.
@@ -2406,16 +2829,19 @@ lazy_static! {
             Attribute::AccountValidFrom,
             Attribute::ApiTokenSession,
             Attribute::UserAuthTokenSession,
+            Attribute::JwtPublicKey,
         ],
         modify_present_attrs: vec![
             Attribute::DisplayName,
             Attribute::SshPublicKey,
+            Attribute::SshPublicKeyExpiry,
             Attribute::PrimaryCredential,
             // Should this be a thing? I think no?
             // Attribute::UnixPassword,
             Attribute::AccountExpire,
             Attribute::AccountValidFrom,
             Attribute::ApiTokenSession,
+            Attribute::JwtPublicKey,
         ],
         ..Default::default()
     };
@@ -2644,3 +3070,886 @@ lazy_static! {
         ..Default::default()
     };
 }
+
+lazy_static! {
+    pub static ref IDM_ACP_OAUTH2_CLIENT_REGISTER_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+        ],
+        name: "idm_acp_oauth2_client_register",
+        uuid: UUID_IDM_ACP_OAUTH2_CLIENT_REGISTER,
+        description: "Builtin IDM Control for dynamic registration of OAuth2 clients.",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_OAUTH2_CLIENT_REGISTRARS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::OAuth2ResourceServer),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::OAuth2RsOrigin,
+            Attribute::OAuth2RsOriginLanding,
+            Attribute::OAuth2StrictRedirectUri,
+            Attribute::OAuth2RsScopeMap,
+        ],
+        create_classes: vec![
+            EntryClass::Object,
+            EntryClass::Account,
+            EntryClass::OAuth2ResourceServer,
+            EntryClass::OAuth2ResourceServerBasic,
+            EntryClass::OAuth2ResourceServerPublic,
+            EntryClass::OAuth2ResourceServerNative,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_OAUTH2_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlDelete,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_oauth2_manage",
+        uuid: UUID_IDM_ACP_OAUTH2_MANAGE_V1,
+        description: "Builtin IDM Control for managing OAuth2 resource server integrations.",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_OAUTH2_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::OAuth2ResourceServer),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::Name,
+            Attribute::Spn,
+            Attribute::OAuth2Session,
+            Attribute::OAuth2RsOrigin,
+            Attribute::OAuth2RsOriginLanding,
+            Attribute::OAuth2RsScopeMap,
+            Attribute::OAuth2RsSupScopeMap,
+            Attribute::OAuth2RsBasicSecret,
+            Attribute::OAuth2RsBasicSecretExpiry,
+            Attribute::OAuth2RsBasicSecretLastUsed,
+            Attribute::OAuth2RsBasicSecretPrevious,
+            Attribute::OAuth2RsBasicSecretPreviousExpiry,
+            Attribute::OAuth2RsBasicSecretPreviousLastUsed,
+            Attribute::OAuth2RsTokenKey,
+            Attribute::Es256PrivateKeyDer,
+            Attribute::OAuth2AllowInsecureClientDisablePkce,
+            Attribute::Rs256PrivateKeyDer,
+            Attribute::OAuth2JwtLegacyCryptoEnable,
+            Attribute::OAuth2JwtSignAlg,
+            Attribute::OAuth2PreferShortUsername,
+            Attribute::OAuth2AllowLocalhostRedirect,
+            Attribute::OAuth2RsClaimMap,
+            Attribute::Image,
+            Attribute::OAuth2StrictRedirectUri,
+            Attribute::OAuth2DeviceFlowEnable,
+            Attribute::OAuth2TokenExchangeAudience,
+            Attribute::OAuth2AllowInsecureRefreshTokenReuse,
+            Attribute::OAuth2RedirectUriMode,
+            Attribute::OAuth2AllowedGrantTypes,
+            Attribute::OAuth2AllowedResponseTypes,
+            Attribute::OAuth2RsScopeDescription,
+            Attribute::OAuth2RsComputedClaim,
+            Attribute::OAuth2RsAssignedMember,
+            Attribute::EntryManagedBy,
+        ],
+        modify_removed_attrs: vec![
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::Name,
+            Attribute::OAuth2Session,
+            Attribute::OAuth2RsOrigin,
+            Attribute::OAuth2RsOriginLanding,
+            Attribute::OAuth2RsScopeMap,
+            Attribute::OAuth2RsSupScopeMap,
+            Attribute::OAuth2RsBasicSecret,
+            Attribute::OAuth2RsBasicSecretExpiry,
+            Attribute::OAuth2RsBasicSecretLastUsed,
+            Attribute::OAuth2RsBasicSecretPrevious,
+            Attribute::OAuth2RsBasicSecretPreviousExpiry,
+            Attribute::OAuth2RsBasicSecretPreviousLastUsed,
+            Attribute::OAuth2RsTokenKey,
+            Attribute::Es256PrivateKeyDer,
+            Attribute::OAuth2AllowInsecureClientDisablePkce,
+            Attribute::Rs256PrivateKeyDer,
+            Attribute::OAuth2JwtLegacyCryptoEnable,
+            Attribute::OAuth2JwtSignAlg,
+            Attribute::OAuth2PreferShortUsername,
+            Attribute::OAuth2AllowLocalhostRedirect,
+            Attribute::OAuth2RsClaimMap,
+            Attribute::Image,
+            Attribute::OAuth2StrictRedirectUri,
+            Attribute::OAuth2DeviceFlowEnable,
+            Attribute::OAuth2TokenExchangeAudience,
+            Attribute::OAuth2AllowInsecureRefreshTokenReuse,
+            Attribute::OAuth2RedirectUriMode,
+            Attribute::OAuth2AllowedGrantTypes,
+            Attribute::OAuth2AllowedResponseTypes,
+            Attribute::OAuth2RsScopeDescription,
+            Attribute::OAuth2RsComputedClaim,
+            Attribute::OAuth2RsAssignedMember,
+            Attribute::EntryManagedBy,
+            Attribute::KeyActionRevoke,
+            Attribute::KeyActionRotate,
+        ],
+        modify_present_attrs: vec![
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::Name,
+            Attribute::OAuth2RsOrigin,
+            Attribute::OAuth2RsOriginLanding,
+            Attribute::OAuth2RsSupScopeMap,
+            Attribute::OAuth2RsScopeMap,
+            Attribute::OAuth2AllowInsecureClientDisablePkce,
+            Attribute::OAuth2JwtLegacyCryptoEnable,
+            Attribute::OAuth2JwtSignAlg,
+            Attribute::OAuth2PreferShortUsername,
+            Attribute::OAuth2AllowLocalhostRedirect,
+            Attribute::OAuth2RsClaimMap,
+            Attribute::Image,
+            Attribute::OAuth2StrictRedirectUri,
+            Attribute::OAuth2DeviceFlowEnable,
+            Attribute::OAuth2TokenExchangeAudience,
+            Attribute::OAuth2AllowInsecureRefreshTokenReuse,
+            Attribute::OAuth2RedirectUriMode,
+            Attribute::OAuth2AllowedGrantTypes,
+            Attribute::OAuth2AllowedResponseTypes,
+            Attribute::OAuth2RsScopeDescription,
+            Attribute::OAuth2RsComputedClaim,
+            Attribute::OAuth2RsAssignedMember,
+            Attribute::OAuth2RsBasicSecretExpiry,
+            Attribute::OAuth2RsBasicSecretPreviousExpiry,
+            Attribute::EntryManagedBy,
+            Attribute::KeyActionRevoke,
+            Attribute::KeyActionRotate,
+        ],
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Description,
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::OAuth2RsName,
+            Attribute::OAuth2RsOrigin,
+            Attribute::OAuth2RsOriginLanding,
+            Attribute::OAuth2RsSupScopeMap,
+            Attribute::OAuth2RsScopeMap,
+            Attribute::OAuth2AllowInsecureClientDisablePkce,
+            Attribute::OAuth2JwtLegacyCryptoEnable,
+            Attribute::OAuth2JwtSignAlg,
+            Attribute::OAuth2PreferShortUsername,
+            Attribute::OAuth2AllowLocalhostRedirect,
+            Attribute::OAuth2RsClaimMap,
+            Attribute::Image,
+            Attribute::OAuth2StrictRedirectUri,
+            Attribute::OAuth2DeviceFlowEnable,
+            Attribute::OAuth2TokenExchangeAudience,
+            Attribute::OAuth2AllowInsecureRefreshTokenReuse,
+            Attribute::OAuth2RedirectUriMode,
+            Attribute::OAuth2AllowedGrantTypes,
+            Attribute::OAuth2AllowedResponseTypes,
+            Attribute::OAuth2RsScopeDescription,
+            Attribute::OAuth2RsComputedClaim,
+            Attribute::OAuth2RsAssignedMember,
+            Attribute::OAuth2RsBasicSecretExpiry,
+            Attribute::EntryManagedBy,
+        ],
+        create_classes: vec![
+            EntryClass::Object,
+            EntryClass::Account,
+            EntryClass::OAuth2ResourceServer,
+            EntryClass::OAuth2ResourceServerBasic,
+            EntryClass::OAuth2ResourceServerPublic,
+            EntryClass::OAuth2ResourceServerNative,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_OAUTH2_ENTRY_MANAGER_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_oauth2_entry_manager",
+        uuid: UUID_IDM_ACP_OAUTH2_ENTRY_MANAGER,
+        description: "Builtin IDM Control for allowing EntryManager to read and modify OAuth2 clients",
+        receiver: BuiltinAcpReceiver::EntryManager,
+        // OAuth2 clients that belong to the Entry Manager.
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::OAuth2ResourceServer),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::Name,
+            Attribute::Spn,
+            Attribute::OAuth2Session,
+            Attribute::OAuth2RsOrigin,
+            Attribute::OAuth2RsOriginLanding,
+            Attribute::OAuth2RsScopeMap,
+            Attribute::OAuth2RsSupScopeMap,
+            Attribute::OAuth2RsBasicSecret,
+            Attribute::OAuth2RsBasicSecretExpiry,
+            Attribute::OAuth2RsBasicSecretLastUsed,
+            Attribute::OAuth2RsBasicSecretPrevious,
+            Attribute::OAuth2RsBasicSecretPreviousExpiry,
+            Attribute::OAuth2RsBasicSecretPreviousLastUsed,
+            Attribute::OAuth2AllowInsecureClientDisablePkce,
+            Attribute::OAuth2JwtLegacyCryptoEnable,
+            Attribute::OAuth2PreferShortUsername,
+            Attribute::OAuth2AllowLocalhostRedirect,
+            Attribute::OAuth2RsClaimMap,
+            Attribute::Image,
+            Attribute::OAuth2StrictRedirectUri,
+            Attribute::OAuth2DeviceFlowEnable,
+            Attribute::OAuth2TokenExchangeAudience,
+            Attribute::OAuth2AllowInsecureRefreshTokenReuse,
+            Attribute::OAuth2RedirectUriMode,
+            Attribute::OAuth2AllowedGrantTypes,
+            Attribute::OAuth2AllowedResponseTypes,
+            Attribute::OAuth2RsScopeDescription,
+            Attribute::OAuth2RsComputedClaim,
+            Attribute::OAuth2RsAssignedMember,
+            Attribute::EntryManagedBy,
+        ],
+        // Entry managers may change who can access the client and how it's presented, but
+        // not weaken its security settings, rename it or change its keys.
+        modify_removed_attrs: vec![
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::OAuth2RsOrigin,
+            Attribute::OAuth2RsOriginLanding,
+            Attribute::OAuth2RsScopeMap,
+            Attribute::OAuth2RsSupScopeMap,
+            Attribute::OAuth2PreferShortUsername,
+            Attribute::OAuth2RsClaimMap,
+            Attribute::Image,
+            Attribute::OAuth2RsAssignedMember,
+        ],
+        modify_present_attrs: vec![
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::OAuth2RsOrigin,
+            Attribute::OAuth2RsOriginLanding,
+            Attribute::OAuth2RsScopeMap,
+            Attribute::OAuth2RsSupScopeMap,
+            Attribute::OAuth2PreferShortUsername,
+            Attribute::OAuth2RsClaimMap,
+            Attribute::Image,
+            Attribute::OAuth2RsAssignedMember,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_WEBHOOK_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlDelete,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_webhook_manage",
+        uuid: UUID_IDM_ACP_WEBHOOK_MANAGE,
+        description: "Builtin IDM Control for managing webhooks",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_SYSTEM_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Webhook),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::WebhookUrl,
+            Attribute::WebhookSecret,
+            Attribute::WebhookFilter,
+        ],
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::WebhookUrl,
+            Attribute::WebhookFilter,
+        ],
+        create_classes: vec![EntryClass::Object, EntryClass::Webhook],
+        modify_present_attrs: vec![
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::WebhookUrl,
+            Attribute::WebhookFilter,
+        ],
+        // Removing the secret causes a new one to be generated.
+        modify_removed_attrs: vec![
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::DisplayName,
+            Attribute::WebhookUrl,
+            Attribute::WebhookSecret,
+            Attribute::WebhookFilter,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_HBAC_RULE_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlDelete,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_hbac_rule_manage",
+        uuid: UUID_IDM_ACP_HBAC_RULE_MANAGE,
+        description: "Builtin IDM Control for managing host based access control rules",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_UNIX_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::HbacRule),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::HbacMember,
+            Attribute::HbacHost,
+            Attribute::HbacService,
+            Attribute::HbacDeny,
+            Attribute::HbacHostGroup,
+        ],
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::HbacMember,
+            Attribute::HbacHost,
+            Attribute::HbacService,
+            Attribute::HbacDeny,
+            Attribute::HbacHostGroup,
+        ],
+        create_classes: vec![EntryClass::Object, EntryClass::HbacRule],
+        modify_present_attrs: vec![
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::HbacMember,
+            Attribute::HbacHost,
+            Attribute::HbacService,
+            Attribute::HbacDeny,
+            Attribute::HbacHostGroup,
+        ],
+        modify_removed_attrs: vec![
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::HbacMember,
+            Attribute::HbacHost,
+            Attribute::HbacService,
+            Attribute::HbacDeny,
+            Attribute::HbacHostGroup,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_HOST_GROUP_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlDelete,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_host_group_manage",
+        uuid: UUID_IDM_ACP_HOST_GROUP_MANAGE,
+        description: "Builtin IDM Control for managing host groups",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_UNIX_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::HostGroup),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::HostGroupMember,
+            Attribute::HostTag,
+        ],
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::HostGroupMember,
+            Attribute::HostTag,
+        ],
+        create_classes: vec![EntryClass::Object, EntryClass::HostGroup],
+        modify_present_attrs: vec![
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::HostGroupMember,
+            Attribute::HostTag,
+        ],
+        modify_removed_attrs: vec![
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::HostGroupMember,
+            Attribute::HostTag,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_HOST_TAG_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_host_tag_manage",
+        uuid: UUID_IDM_ACP_HOST_TAG_MANAGE,
+        description: "Builtin IDM Control for managing the host tags of machine accounts",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_UNIX_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::ServiceAccount),
+            FILTER_ANDNOT_HP_OR_RECYCLED_OR_TOMBSTONE.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Spn,
+            Attribute::HostTag,
+        ],
+        modify_present_attrs: vec![Attribute::HostTag],
+        modify_removed_attrs: vec![Attribute::HostTag],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_HOST_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlDelete,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_host_manage",
+        uuid: UUID_IDM_ACP_HOST_MANAGE,
+        description: "Builtin IDM Control for managing and enrolling hosts",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_UNIX_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Host),
+            FILTER_ANDNOT_HP_OR_RECYCLED_OR_TOMBSTONE.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Spn,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::HostTag,
+            Attribute::HostJoinTokenExpiry,
+            Attribute::HostKioskMode,
+            Attribute::HostKioskCacheTimeout,
+            Attribute::HostKioskGuestGroup,
+            Attribute::KerberosServicePrincipal,
+            Attribute::ApiTokenSession,
+        ],
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::HostTag,
+            Attribute::HostKioskMode,
+            Attribute::HostKioskCacheTimeout,
+            Attribute::HostKioskGuestGroup,
+            Attribute::KerberosServicePrincipal,
+        ],
+        create_classes: vec![
+            EntryClass::Object,
+            EntryClass::Account,
+            EntryClass::ServiceAccount,
+            EntryClass::Host,
+        ],
+        modify_present_attrs: vec![
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::HostTag,
+            Attribute::HostJoinToken,
+            Attribute::HostJoinTokenExpiry,
+            Attribute::HostKioskMode,
+            Attribute::HostKioskCacheTimeout,
+            Attribute::HostKioskGuestGroup,
+            Attribute::KerberosServicePrincipal,
+        ],
+        modify_removed_attrs: vec![
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::HostTag,
+            Attribute::HostJoinToken,
+            Attribute::HostJoinTokenExpiry,
+            Attribute::HostKioskMode,
+            Attribute::HostKioskCacheTimeout,
+            Attribute::HostKioskGuestGroup,
+            Attribute::KerberosServicePrincipal,
+            Attribute::ApiTokenSession,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_HOST_SELF_READ_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlSearch,
+        ],
+        name: "idm_acp_host_self_read",
+        uuid: UUID_IDM_ACP_HOST_SELF_READ,
+        description: "Builtin IDM Control for hosts to read their own kiosk configuration",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_ALL_ACCOUNTS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Host),
+            ProtoFilter::SelfUuid,
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::HostKioskMode,
+            Attribute::HostKioskCacheTimeout,
+            Attribute::HostKioskGuestGroup,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_MIGRATION_READ_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_migration_read",
+        uuid: UUID_IDM_ACP_MIGRATION_READ,
+        description: "Builtin IDM Control for reading the records of applied migrations",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_SYSTEM_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::MigrationRecord),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::MigrationName,
+            Attribute::MigrationFromLevel,
+            Attribute::MigrationToLevel,
+            Attribute::MigrationAppliedAt,
+            Attribute::MigrationDuration,
+            Attribute::MigrationEntriesChanged,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_ANNOUNCEMENT_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlDelete,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_announcement_manage",
+        uuid: UUID_IDM_ACP_ANNOUNCEMENT_MANAGE,
+        description: "Builtin IDM Control for managing announcements",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_SYSTEM_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Announcement),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::AnnouncementMessage,
+            Attribute::AnnouncementSeverity,
+            Attribute::AnnouncementStart,
+            Attribute::AnnouncementEnd,
+            Attribute::AnnouncementCli,
+        ],
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::AnnouncementMessage,
+            Attribute::AnnouncementSeverity,
+            Attribute::AnnouncementStart,
+            Attribute::AnnouncementEnd,
+            Attribute::AnnouncementCli,
+        ],
+        create_classes: vec![EntryClass::Object, EntryClass::Announcement],
+        modify_present_attrs: vec![
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::AnnouncementMessage,
+            Attribute::AnnouncementSeverity,
+            Attribute::AnnouncementStart,
+            Attribute::AnnouncementEnd,
+            Attribute::AnnouncementCli,
+        ],
+        modify_removed_attrs: vec![
+            Attribute::Name,
+            Attribute::Description,
+            Attribute::AnnouncementMessage,
+            Attribute::AnnouncementSeverity,
+            Attribute::AnnouncementStart,
+            Attribute::AnnouncementEnd,
+            Attribute::AnnouncementCli,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_OIDC_UPSTREAM_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlDelete,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_oidc_upstream_manage",
+        uuid: UUID_IDM_ACP_OIDC_UPSTREAM_MANAGE,
+        description: "Builtin IDM Control for managing upstream OpenID Connect providers",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_SYSTEM_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::OidcUpstream),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        // The client secret can be replaced, but not read back.
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::OidcUpstreamIssuer,
+            Attribute::OidcUpstreamClientId,
+            Attribute::OidcUpstreamAuthorisationEndpoint,
+            Attribute::OidcUpstreamTokenEndpoint,
+            Attribute::OidcUpstreamJwksUri,
+        ],
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::OidcUpstreamIssuer,
+            Attribute::OidcUpstreamClientId,
+            Attribute::OidcUpstreamAuthorisationEndpoint,
+            Attribute::OidcUpstreamTokenEndpoint,
+            Attribute::OidcUpstreamJwksUri,
+            Attribute::OidcUpstreamClientSecret,
+        ],
+        create_classes: vec![EntryClass::Object, EntryClass::OidcUpstream],
+        modify_present_attrs: vec![
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::OidcUpstreamIssuer,
+            Attribute::OidcUpstreamClientId,
+            Attribute::OidcUpstreamAuthorisationEndpoint,
+            Attribute::OidcUpstreamTokenEndpoint,
+            Attribute::OidcUpstreamJwksUri,
+            Attribute::OidcUpstreamClientSecret,
+        ],
+        modify_removed_attrs: vec![
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::OidcUpstreamIssuer,
+            Attribute::OidcUpstreamClientId,
+            Attribute::OidcUpstreamAuthorisationEndpoint,
+            Attribute::OidcUpstreamTokenEndpoint,
+            Attribute::OidcUpstreamJwksUri,
+            Attribute::OidcUpstreamClientSecret,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_SAML2_MANAGE_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlCreate,
+            EntryClass::AccessControlDelete,
+            EntryClass::AccessControlModify,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_saml2_manage",
+        uuid: UUID_IDM_ACP_SAML2_MANAGE,
+        description: "Builtin IDM Control for managing SAML service providers",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_SAML2_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Saml2ServiceProvider),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        // The signing keys are held in the key object of each service provider. They can be
+        // rotated and revoked, but never read.
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::Saml2EntityId,
+            Attribute::Saml2AcsUrl,
+            Attribute::Saml2GroupMap,
+        ],
+        create_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::Saml2EntityId,
+            Attribute::Saml2AcsUrl,
+        ],
+        create_classes: vec![
+            EntryClass::Object,
+            EntryClass::Saml2ServiceProvider,
+            EntryClass::KeyObject,
+            EntryClass::KeyObjectSaml2Signing,
+        ],
+        modify_present_attrs: vec![
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::Saml2EntityId,
+            Attribute::Saml2AcsUrl,
+            Attribute::Saml2GroupMap,
+            Attribute::KeyActionRevoke,
+            Attribute::KeyActionRotate,
+        ],
+        modify_removed_attrs: vec![
+            Attribute::Name,
+            Attribute::DisplayName,
+            Attribute::Description,
+            Attribute::Saml2EntityId,
+            Attribute::Saml2AcsUrl,
+            Attribute::Saml2GroupMap,
+            Attribute::KeyActionRevoke,
+            Attribute::KeyActionRotate,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_WHITE_PAGES_READ_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlSearch,
+        ],
+        name: "idm_acp_white_pages_read",
+        uuid: UUID_IDM_ACP_WHITE_PAGES_READ,
+        description: "Builtin IDM Control for reading the directory listing of persons. Members of idm_white_pages_readers may read the name, mail and phone of persons - anonymous may be added to allow address book clients to search without a credential.",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_WHITE_PAGES_READERS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::Person),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Name,
+            Attribute::Uuid,
+            Attribute::Spn,
+            Attribute::DisplayName,
+            Attribute::Mail,
+            Attribute::Phone,
+        ],
+        ..Default::default()
+    };
+}
+
+lazy_static! {
+    pub static ref IDM_ACP_GROUP_MEMBERSHIP_REQUEST_READ_DL10: BuiltinAcp = BuiltinAcp {
+        classes: vec![
+            EntryClass::Object,
+            EntryClass::AccessControlProfile,
+            EntryClass::AccessControlSearch
+        ],
+        name: "idm_acp_group_membership_request_read",
+        uuid: UUID_IDM_ACP_GROUP_MEMBERSHIP_REQUEST_READ,
+        description: "Builtin IDM Control for reviewing the requests that have been made to join groups",
+        receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_GROUP_ADMINS]),
+        target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+            match_class_filter!(EntryClass::GroupMembershipRequest),
+            FILTER_ANDNOT_TOMBSTONE_OR_RECYCLED.clone(),
+        ])),
+        search_attrs: vec![
+            Attribute::Class,
+            Attribute::Uuid,
+            Attribute::Description,
+            Attribute::MembershipRequestGroup,
+            Attribute::MembershipRequester,
+            Attribute::MembershipRequestStatus,
+            Attribute::MembershipRequestDecidedBy,
+            Attribute::MembershipRequestValidUntil,
+        ],
+        ..Default::default()
+    };
+}
@@ -212,6 +212,27 @@ lazy_static! {
         ..Default::default()
     };
 
+    /// Builtin IDM Group for managing saml2 service provider integrations to this authentication domain.
+    pub static ref BUILTIN_GROUP_SAML2_ADMINS_DL10: BuiltinGroup = BuiltinGroup {
+        name: "idm_saml2_admins",
+        description: "Builtin SAML Integration Administration Group.",
+        uuid: UUID_IDM_SAML2_ADMINS,
+        entry_managed_by: Some(UUID_IDM_ADMINS),
+        members: vec![UUID_IDM_ADMINS],
+        ..Default::default()
+    };
+
+    /// Builtin IDM Group for issuing impersonation sessions of persons. This has no members by
+    /// default, as every use of it should be a deliberate decision.
+    pub static ref BUILTIN_GROUP_IMPERSONATION_ADMINS_DL10: BuiltinGroup = BuiltinGroup {
+        name: "idm_impersonation_admins",
+        description: "Builtin IDM Group for impersonating persons to investigate issues they report.",
+        uuid: UUID_IDM_IMPERSONATION_ADMINS,
+        entry_managed_by: Some(UUID_IDM_ADMINS),
+        members: Vec::with_capacity(0),
+        ..Default::default()
+    };
+
     pub static ref BUILTIN_GROUP_RADIUS_SERVICE_ADMINS: BuiltinGroup = BuiltinGroup {
         name: "idm_radius_service_admins",
         description: "Builtin Radius Administration Group.",
@@ -380,6 +401,8 @@ lazy_static! {
             UUID_IDM_SCHEMA_ADMINS,
             UUID_IDM_ACCESS_CONTROL_ADMINS,
             UUID_IDM_OAUTH2_ADMINS,
+            UUID_IDM_SAML2_ADMINS,
+            UUID_IDM_IMPERSONATION_ADMINS,
             UUID_IDM_RADIUS_ADMINS,
             UUID_IDM_ACCOUNT_POLICY_ADMINS,
             UUID_IDM_RADIUS_SERVERS,
@@ -405,4 +428,23 @@ lazy_static! {
         members: vec![UUID_IDM_ADMINS],
         ..Default::default()
     };
+
+    /// Builtin IDM Group for delegating OAuth2 dynamic client registration to automation.
+    pub static ref BUILTIN_GROUP_OAUTH2_CLIENT_REGISTRARS_DL10: BuiltinGroup = BuiltinGroup {
+        name: "idm_oauth2_client_registrars",
+        uuid: UUID_IDM_OAUTH2_CLIENT_REGISTRARS,
+        description: "Builtin OAuth2 Dynamic Client Registration Group.",
+        entry_managed_by: Some(UUID_IDM_OAUTH2_ADMINS),
+        ..Default::default()
+    };
+
+    /// Members of this group may read the directory listing of persons. Anonymous is not a
+    /// member by default, and must be added to allow address book clients to search.
+    pub static ref BUILTIN_GROUP_WHITE_PAGES_READERS_DL10: BuiltinGroup = BuiltinGroup {
+        name: "idm_white_pages_readers",
+        uuid: UUID_IDM_WHITE_PAGES_READERS,
+        description: "Builtin IDM Group granting read of the name, mail and phone of all persons.",
+        entry_managed_by: Some(UUID_IDM_ACCESS_CONTROL_ADMINS),
+        ..Default::default()
+    };
 }
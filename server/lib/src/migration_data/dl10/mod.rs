@@ -106,6 +106,106 @@ pub fn phase_1_schema_attrs() -> Vec<EntryInitNew> {
         // DL10
         SCHEMA_ATTR_DENIED_NAME_DL10.clone().into(),
         SCHEMA_ATTR_LDAP_MAXIMUM_QUERYABLE_ATTRIBUTES.clone().into(),
+        SCHEMA_ATTR_AUTH_TRUSTED_NETWORK_DL10.clone().into(),
+        SCHEMA_ATTR_CREDENTIAL_TYPE_MINIMUM_UNTRUSTED_NETWORK_DL10
+            .clone()
+            .into(),
+        SCHEMA_ATTR_SPONSOR_DL10.clone().into(),
+        SCHEMA_ATTR_CREDENTIAL_USAGE_DL10.clone().into(),
+        SCHEMA_ATTR_AUTH_LOCKOUT_THRESHOLD_DL10.clone().into(),
+        SCHEMA_ATTR_AUTH_LOCKOUT_WINDOW_DL10.clone().into(),
+        SCHEMA_ATTR_AUTH_LOCKOUT_DURATION_DL10.clone().into(),
+        SCHEMA_ATTR_AUTH_PASSWORD_HISTORY_COUNT_DL10.clone().into(),
+        SCHEMA_ATTR_ALLOW_UNIX_PASSWORD_DL10.clone().into(),
+        SCHEMA_ATTR_SSH_PUBLICKEY_EXPIRY_DL10.clone().into(),
+        SCHEMA_ATTR_SSH_KEY_ALLOWED_TYPE_DL10.clone().into(),
+        SCHEMA_ATTR_SSH_KEY_RSA_MINIMUM_BITS_DL10.clone().into(),
+        SCHEMA_ATTR_LDAP_GROUP_COMPAT_DL10.clone().into(),
+        SCHEMA_ATTR_POSIX_DEFAULT_SHELL_DL10.clone().into(),
+        SCHEMA_ATTR_POSIX_HOME_TEMPLATE_DL10.clone().into(),
+        SCHEMA_ATTR_POSIX_GECOS_FORMAT_DL10.clone().into(),
+        SCHEMA_ATTR_POSIX_HOME_SKELETON_DL10.clone().into(),
+        SCHEMA_ATTR_POSIX_HOME_QUOTA_DL10.clone().into(),
+        SCHEMA_ATTR_PASSWORD_HISTORY_DL10.clone().into(),
+        SCHEMA_ATTR_DENIED_PASSWORD_TERM_DL10.clone().into(),
+        SCHEMA_ATTR_WEBHOOK_URL_DL10.clone().into(),
+        SCHEMA_ATTR_WEBHOOK_SECRET_DL10.clone().into(),
+        SCHEMA_ATTR_WEBHOOK_FILTER_DL10.clone().into(),
+        SCHEMA_ATTR_HBAC_MEMBER_DL10.clone().into(),
+        SCHEMA_ATTR_HBAC_HOST_DL10.clone().into(),
+        SCHEMA_ATTR_HBAC_SERVICE_DL10.clone().into(),
+        SCHEMA_ATTR_HBAC_DENY_DL10.clone().into(),
+        SCHEMA_ATTR_HOST_TAG_DL10.clone().into(),
+        SCHEMA_ATTR_HOST_GROUP_MEMBER_DL10.clone().into(),
+        SCHEMA_ATTR_HBAC_HOST_GROUP_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE_DL10.clone().into(),
+        SCHEMA_ATTR_NOTIFICATION_OPT_OUT_DL10.clone().into(),
+        SCHEMA_ATTR_ACCOUNT_LIFETIME_DL10.clone().into(),
+        SCHEMA_ATTR_ACCOUNT_ARCHIVE_DELAY_DL10.clone().into(),
+        SCHEMA_ATTR_DOMAIN_THEME_DL10.clone().into(),
+        SCHEMA_ATTR_UI_THEME_DL10.clone().into(),
+        SCHEMA_ATTR_ACP_TARGET_GROUP_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_REDIRECT_URI_MODE_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_ALLOWED_GRANT_TYPES_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_RS_SCOPE_DESCRIPTION_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_LAST_USED_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_RS_ASSIGNED_MEMBER_DL10.clone().into(),
+        SCHEMA_ATTR_HOST_JOIN_TOKEN_DL10.clone().into(),
+        SCHEMA_ATTR_HOST_JOIN_TOKEN_EXPIRY_DL10.clone().into(),
+        SCHEMA_ATTR_ALLOW_API_TOKENS_DL10.clone().into(),
+        SCHEMA_ATTR_LOGIN_HOST_TAG_DL10.clone().into(),
+        SCHEMA_ATTR_DOMAIN_LOGIN_BANNER_DL10.clone().into(),
+        SCHEMA_ATTR_DOMAIN_PRIMARY_COLOUR_DL10.clone().into(),
+        SCHEMA_ATTR_HOST_KIOSK_MODE_DL10.clone().into(),
+        SCHEMA_ATTR_HOST_KIOSK_CACHE_TIMEOUT_DL10.clone().into(),
+        SCHEMA_ATTR_HOST_KIOSK_GUEST_GROUP_DL10.clone().into(),
+        SCHEMA_ATTR_MIGRATION_NAME_DL10.clone().into(),
+        SCHEMA_ATTR_MIGRATION_FROM_LEVEL_DL10.clone().into(),
+        SCHEMA_ATTR_MIGRATION_TO_LEVEL_DL10.clone().into(),
+        SCHEMA_ATTR_MIGRATION_APPLIED_AT_DL10.clone().into(),
+        SCHEMA_ATTR_MIGRATION_DURATION_DL10.clone().into(),
+        SCHEMA_ATTR_MIGRATION_ENTRIES_CHANGED_DL10.clone().into(),
+        SCHEMA_ATTR_ALLOW_EMAIL_LINK_DL10.clone().into(),
+        SCHEMA_ATTR_ANNOUNCEMENT_MESSAGE_DL10.clone().into(),
+        SCHEMA_ATTR_ANNOUNCEMENT_SEVERITY_DL10.clone().into(),
+        SCHEMA_ATTR_ANNOUNCEMENT_START_DL10.clone().into(),
+        SCHEMA_ATTR_ANNOUNCEMENT_END_DL10.clone().into(),
+        SCHEMA_ATTR_ANNOUNCEMENT_CLI_DL10.clone().into(),
+        SCHEMA_ATTR_OIDC_UPSTREAM_ISSUER_DL10.clone().into(),
+        SCHEMA_ATTR_OIDC_UPSTREAM_CLIENT_ID_DL10.clone().into(),
+        SCHEMA_ATTR_OIDC_UPSTREAM_CLIENT_SECRET_DL10.clone().into(),
+        SCHEMA_ATTR_OIDC_UPSTREAM_AUTHORISATION_ENDPOINT_DL10.clone().into(),
+        SCHEMA_ATTR_OIDC_UPSTREAM_TOKEN_ENDPOINT_DL10.clone().into(),
+        SCHEMA_ATTR_OIDC_UPSTREAM_JWKS_URI_DL10.clone().into(),
+        SCHEMA_ATTR_PHONE_DL10.clone().into(),
+        SCHEMA_ATTR_WHITE_PAGES_TRUSTED_NETWORK_DL10.clone().into(),
+        SCHEMA_ATTR_WHITE_PAGES_RATE_LIMIT_DL10.clone().into(),
+        SCHEMA_ATTR_SAML2_ENTITY_ID_DL10.clone().into(),
+        SCHEMA_ATTR_SAML2_ACS_URL_DL10.clone().into(),
+        SCHEMA_ATTR_SAML2_GROUP_MAP_DL10.clone().into(),
+        SCHEMA_ATTR_KERBEROS_SERVICE_PRINCIPAL_DL10.clone().into(),
+        SCHEMA_ATTR_SYNC_CONFLICT_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_JWT_SIGN_ALG_DL10.clone().into(),
+        SCHEMA_ATTR_OAUTH2_RS_COMPUTED_CLAIM_DL10.clone().into(),
+        SCHEMA_ATTR_MEMBER_VALID_UNTIL_DL10.clone().into(),
+        SCHEMA_ATTR_MEMBERSHIP_REQUESTABLE_DL10.clone().into(),
+        SCHEMA_ATTR_MEMBERSHIP_REQUEST_GROUP_DL10.clone().into(),
+        SCHEMA_ATTR_MEMBERSHIP_REQUESTER_DL10.clone().into(),
+        SCHEMA_ATTR_MEMBERSHIP_REQUEST_STATUS_DL10.clone().into(),
+        SCHEMA_ATTR_MEMBERSHIP_REQUEST_DECIDED_BY_DL10.clone().into(),
+        SCHEMA_ATTR_MEMBERSHIP_REQUEST_VALID_UNTIL_DL10.clone().into(),
+        SCHEMA_ATTR_PARENT_GROUP_DL10.clone().into(),
+        SCHEMA_ATTR_PASSWORD_BREACH_CHECK_DL10.clone().into(),
+        SCHEMA_ATTR_AUTH_PASSWORD_MAXIMUM_AGE_DL10.clone().into(),
+        SCHEMA_ATTR_PASSWORD_LAST_CHANGED_DL10.clone().into(),
+        SCHEMA_ATTR_AUTH_SESSION_IDLE_TIMEOUT_DL10.clone().into(),
+        SCHEMA_ATTR_JWT_PUBLIC_KEY_DL10.clone().into(),
     ]
 }
 
@@ -119,7 +219,6 @@ pub fn phase_2_schema_classes() -> Vec<EntryInitNew> {
         // DL4
         SCHEMA_CLASS_OAUTH2_RS_PUBLIC_DL4.clone().into(),
         // DL5
-        SCHEMA_CLASS_ACCOUNT_DL5.clone().into(),
         SCHEMA_CLASS_OAUTH2_RS_BASIC_DL5.clone().into(),
         // DL6
         SCHEMA_CLASS_GROUP_DL6.clone().into(),
@@ -127,20 +226,37 @@ pub fn phase_2_schema_classes() -> Vec<EntryInitNew> {
         SCHEMA_CLASS_KEY_PROVIDER_INTERNAL_DL6.clone().into(),
         SCHEMA_CLASS_KEY_OBJECT_DL6.clone().into(),
         SCHEMA_CLASS_KEY_OBJECT_JWT_ES256_DL6.clone().into(),
+        SCHEMA_CLASS_KEY_OBJECT_JWT_RS256_DL10.clone().into(),
         SCHEMA_CLASS_KEY_OBJECT_JWE_A128GCM_DL6.clone().into(),
         SCHEMA_CLASS_KEY_OBJECT_INTERNAL_DL6.clone().into(),
         // DL7
-        SCHEMA_CLASS_SERVICE_ACCOUNT_DL7.clone().into(),
-        SCHEMA_CLASS_SYNC_ACCOUNT_DL7.clone().into(),
+        SCHEMA_CLASS_SERVICE_ACCOUNT_DL10.clone().into(),
+        SCHEMA_CLASS_SYNC_ACCOUNT_DL10.clone().into(),
         SCHEMA_CLASS_CLIENT_CERTIFICATE_DL7.clone().into(),
         // DL8
-        SCHEMA_CLASS_ACCOUNT_POLICY_DL8.clone().into(),
         SCHEMA_CLASS_APPLICATION_DL8.clone().into(),
-        SCHEMA_CLASS_PERSON_DL8.clone().into(),
-        // DL9
-        SCHEMA_CLASS_OAUTH2_RS_DL9.clone().into(),
+        SCHEMA_CLASS_PERSON_DL10.clone().into(),
         // DL10
+        SCHEMA_CLASS_OAUTH2_RS_DL10.clone().into(),
         SCHEMA_CLASS_DOMAIN_INFO_DL10.clone().into(),
+        SCHEMA_CLASS_ACCOUNT_POLICY_DL10.clone().into(),
+        SCHEMA_CLASS_CONTRACTOR_DL10.clone().into(),
+        SCHEMA_CLASS_ACCOUNT_DL10.clone().into(),
+        SCHEMA_CLASS_WEBHOOK_DL10.clone().into(),
+        SCHEMA_CLASS_HBAC_RULE_DL10.clone().into(),
+        SCHEMA_CLASS_HOST_GROUP_DL10.clone().into(),
+        SCHEMA_CLASS_ACCESS_CONTROL_TARGET_GROUP_DL10.clone().into(),
+        SCHEMA_CLASS_KEY_OBJECT_REPL_TRUST_ANCHOR_DL10.clone().into(),
+        SCHEMA_CLASS_KEY_OBJECT_SSH_CA_DL10.clone().into(),
+        SCHEMA_CLASS_HOST_DL10.clone().into(),
+        SCHEMA_CLASS_OAUTH2_RS_NATIVE_DL10.clone().into(),
+        SCHEMA_CLASS_MIGRATION_RECORD_DL10.clone().into(),
+        SCHEMA_CLASS_ANNOUNCEMENT_DL10.clone().into(),
+        SCHEMA_CLASS_OIDC_UPSTREAM_DL10.clone().into(),
+        SCHEMA_CLASS_KEY_OBJECT_SAML2_SIGNING_DL10.clone().into(),
+        SCHEMA_CLASS_SAML2_SERVICE_PROVIDER_DL10.clone().into(),
+        SCHEMA_CLASS_KEY_OBJECT_KERBEROS_DL10.clone().into(),
+        SCHEMA_CLASS_GROUP_MEMBERSHIP_REQUEST_DL10.clone().into(),
     ]
 }
 
@@ -196,6 +312,11 @@ pub fn phase_6_builtin_non_admin_entries() -> Result<Vec<EntryInitNew>, Operatio
             .clone()
             .try_into()?,
         BUILTIN_GROUP_APPLICATION_ADMINS_DL8.clone().try_into()?,
+        // DL10
+        BUILTIN_GROUP_OAUTH2_CLIENT_REGISTRARS_DL10.clone().try_into()?,
+        BUILTIN_GROUP_WHITE_PAGES_READERS_DL10.clone().try_into()?,
+        BUILTIN_GROUP_SAML2_ADMINS_DL10.clone().try_into()?,
+        BUILTIN_GROUP_IMPERSONATION_ADMINS_DL10.clone().try_into()?,
         // Write deps on read.clone().try_into()?, so write must be added first.
         // All members must exist before we write HP
         IDM_HIGH_PRIVILEGE_DL8.clone().try_into()?,
@@ -230,11 +351,11 @@ pub fn phase_7_builtin_access_control_profiles() -> Vec<EntryInitNew> {
         IDM_ACP_ACCOUNT_UNIX_EXTEND_V1.clone().into(),
         IDM_ACP_PEOPLE_PII_READ_V1.clone().into(),
         IDM_ACP_PEOPLE_PII_MANAGE_V1.clone().into(),
-        IDM_ACP_PEOPLE_READ_V1.clone().into(),
-        IDM_ACP_PEOPLE_MANAGE_V1.clone().into(),
+        IDM_ACP_PEOPLE_READ_DL10.clone().into(),
+        IDM_ACP_PEOPLE_MANAGE_DL10.clone().into(),
         IDM_ACP_PEOPLE_DELETE_V1.clone().into(),
-        IDM_ACP_PEOPLE_CREDENTIAL_RESET_V1.clone().into(),
-        IDM_ACP_HP_PEOPLE_CREDENTIAL_RESET_V1.clone().into(),
+        IDM_ACP_PEOPLE_CREDENTIAL_RESET_DL10.clone().into(),
+        IDM_ACP_HP_PEOPLE_CREDENTIAL_RESET_DL10.clone().into(),
         IDM_ACP_SERVICE_ACCOUNT_CREATE_V1.clone().into(),
         IDM_ACP_SERVICE_ACCOUNT_DELETE_V1.clone().into(),
         IDM_ACP_SERVICE_ACCOUNT_ENTRY_MANAGER_V1.clone().into(),
@@ -248,21 +369,36 @@ pub fn phase_7_builtin_access_control_profiles() -> Vec<EntryInitNew> {
         // DL4
         // DL5
         // DL6
-        IDM_ACP_PEOPLE_CREATE_DL6.clone().into(),
+        IDM_ACP_PEOPLE_CREATE_DL10.clone().into(),
         IDM_ACP_ACCOUNT_MAIL_READ_DL6.clone().into(),
         // DL7
         IDM_ACP_SELF_NAME_WRITE_DL7.clone().into(),
         IDM_ACP_HP_CLIENT_CERTIFICATE_MANAGER_DL7.clone().into(),
         // DL8
-        IDM_ACP_SELF_READ_DL8.clone().into(),
-        IDM_ACP_SELF_WRITE_DL8.clone().into(),
+        IDM_ACP_SELF_WRITE_DL10.clone().into(),
         IDM_ACP_APPLICATION_MANAGE_DL8.clone().into(),
         IDM_ACP_APPLICATION_ENTRY_MANAGER_DL8.clone().into(),
         IDM_ACP_MAIL_SERVERS_DL8.clone().into(),
         IDM_ACP_GROUP_ACCOUNT_POLICY_MANAGE_DL8.clone().into(),
         // DL9
-        IDM_ACP_OAUTH2_MANAGE_DL9.clone().into(),
         IDM_ACP_GROUP_MANAGE_DL9.clone().into(),
         IDM_ACP_DOMAIN_ADMIN_DL9.clone().into(),
+        // DL10
+        IDM_ACP_OAUTH2_CLIENT_REGISTER_DL10.clone().into(),
+        IDM_ACP_OAUTH2_MANAGE_DL10.clone().into(),
+        IDM_ACP_OAUTH2_ENTRY_MANAGER_DL10.clone().into(),
+        IDM_ACP_SELF_READ_DL10.clone().into(),
+        IDM_ACP_WEBHOOK_MANAGE_DL10.clone().into(),
+        IDM_ACP_HBAC_RULE_MANAGE_DL10.clone().into(),
+        IDM_ACP_HOST_GROUP_MANAGE_DL10.clone().into(),
+        IDM_ACP_HOST_TAG_MANAGE_DL10.clone().into(),
+        IDM_ACP_HOST_MANAGE_DL10.clone().into(),
+        IDM_ACP_HOST_SELF_READ_DL10.clone().into(),
+        IDM_ACP_MIGRATION_READ_DL10.clone().into(),
+        IDM_ACP_ANNOUNCEMENT_MANAGE_DL10.clone().into(),
+        IDM_ACP_OIDC_UPSTREAM_MANAGE_DL10.clone().into(),
+        IDM_ACP_WHITE_PAGES_READ_DL10.clone().into(),
+        IDM_ACP_SAML2_MANAGE_DL10.clone().into(),
+        IDM_ACP_GROUP_MEMBERSHIP_REQUEST_READ_DL10.clone().into(),
     ]
 }
@@ -226,6 +226,16 @@ pub static ref SCHEMA_ATTR_DENIED_NAME_DL10: SchemaAttribute = SchemaAttribute {
     ..Default::default()
 };
 
+pub static ref SCHEMA_ATTR_DENIED_PASSWORD_TERM_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_DENIED_PASSWORD_TERM,
+    name: Attribute::DeniedPasswordTerm,
+    description: "Terms that passwords are not allowed to contain, such as the name of the organisation.".to_string(),
+
+    syntax: SyntaxType::Utf8StringInsensitive,
+    multivalue: true,
+    ..Default::default()
+};
+
 pub static ref SCHEMA_ATTR_DOMAIN_TOKEN_KEY: SchemaAttribute = SchemaAttribute {
     uuid: UUID_SCHEMA_ATTR_DOMAIN_TOKEN_KEY,
     name: Attribute::DomainTokenKey,
@@ -496,6 +506,129 @@ pub static ref SCHEMA_ATTR_OAUTH2_DEVICE_FLOW_ENABLE_DL9: SchemaAttribute = Sche
     ..Default::default()
 };
 
+pub static ref SCHEMA_ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_TOKEN_EXCHANGE_AUDIENCE,
+    name: Attribute::OAuth2TokenExchangeAudience,
+    description: "The OAuth2 clients that this client may request tokens for by exchanging a user's access token".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_ALLOW_INSECURE_REFRESH_TOKEN_REUSE,
+    name: Attribute::OAuth2AllowInsecureRefreshTokenReuse,
+    description: "Allows refresh tokens issued to this OAuth2 client to be reused rather than rotated on each refresh".to_string(),
+
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_REDIRECT_URI_MODE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_REDIRECT_URI_MODE,
+    name: Attribute::OAuth2RedirectUriMode,
+    description: "How redirect uris are validated for this OAuth2 client. One of exact, loopback_any_port or private_use_scheme".to_string(),
+
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_ALLOWED_GRANT_TYPES_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_ALLOWED_GRANT_TYPES,
+    name: Attribute::OAuth2AllowedGrantTypes,
+    description: "The grant types this OAuth2 client may use at the token endpoint. If absent, all grant types are allowed".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_ALLOWED_RESPONSE_TYPES,
+    name: Attribute::OAuth2AllowedResponseTypes,
+    description: "The response types this OAuth2 client may request at the authorisation endpoint. If absent, all response types are allowed".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_RS_SCOPE_DESCRIPTION_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_RS_SCOPE_DESCRIPTION,
+    name: Attribute::OAuth2RsScopeDescription,
+    description: "A human readable, optionally localised, title and description of a scope that is displayed on the consent screen".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_RS_ASSIGNED_MEMBER_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_RS_ASSIGNED_MEMBER,
+    name: Attribute::OAuth2RsAssignedMember,
+    description: "The accounts and groups that this OAuth2 client is assigned to. If set, only these may see the client in the apps listing and authorise to it".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_EXPIRY,
+    name: Attribute::OAuth2RsBasicSecretExpiry,
+    description: "The time after which the basic secret of this OAuth2 client is no longer valid".to_string(),
+
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_LAST_USED_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_LAST_USED,
+    name: Attribute::OAuth2RsBasicSecretLastUsed,
+    description: "The time the basic secret of this OAuth2 client was last used to authenticate".to_string(),
+
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS,
+    name: Attribute::OAuth2RsBasicSecretPrevious,
+    description: "The basic secret of this OAuth2 client before it was rotated, that remains valid until its expiry".to_string(),
+
+    syntax: SyntaxType::SecretUtf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_EXPIRY,
+    name: Attribute::OAuth2RsBasicSecretPreviousExpiry,
+    description: "The time after which the previous basic secret of this OAuth2 client is no longer valid".to_string(),
+
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_RS_BASIC_SECRET_PREVIOUS_LAST_USED,
+    name: Attribute::OAuth2RsBasicSecretPreviousLastUsed,
+    description: "The time the previous basic secret of this OAuth2 client was last used to authenticate".to_string(),
+
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_NOTIFICATION_OPT_OUT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_NOTIFICATION_OPT_OUT,
+    name: Attribute::NotificationOptOut,
+    description: "The categories of security notification that the owner of this account has opted out of receiving".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
 pub static ref SCHEMA_ATTR_ES256_PRIVATE_KEY_DER: SchemaAttribute = SchemaAttribute {
     uuid: UUID_SCHEMA_ATTR_ES256_PRIVATE_KEY_DER,
     name: Attribute::Es256PrivateKeyDer,
@@ -825,188 +958,1315 @@ pub static ref SCHEMA_ATTR_ALLOW_PRIMARY_CRED_FALLBACK_DL8: SchemaAttribute = Sc
     ..Default::default()
 };
 
-pub static ref SCHEMA_ATTR_CERTIFICATE_DL7: SchemaAttribute = SchemaAttribute {
-    uuid: UUID_SCHEMA_ATTR_CERTIFICATE,
-    name: Attribute::Certificate,
-    description: "An x509 Certificate".to_string(),
+pub static ref SCHEMA_ATTR_AUTH_TRUSTED_NETWORK_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_AUTH_TRUSTED_NETWORK,
+    name: Attribute::AuthTrustedNetwork,
+    description: "A network in CIDR notation that authentication is trusted from".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_CREDENTIAL_TYPE_MINIMUM_UNTRUSTED_NETWORK_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_CREDENTIAL_TYPE_MINIMUM_UNTRUSTED_NETWORK,
+    name: Attribute::CredentialTypeMinimumUntrustedNetwork,
+    description: "The minimum level of credential type that can authenticate from outside of a trusted network".to_string(),
+
     multivalue: false,
-    syntax: SyntaxType::Certificate,
+    syntax: SyntaxType::CredentialType,
     ..Default::default()
 };
 
-pub static ref SCHEMA_ATTR_APPLICATION_PASSWORD_DL8: SchemaAttribute = SchemaAttribute {
-    uuid: UUID_SCHEMA_ATTR_APPLICATION_PASSWORD,
-    name: Attribute::ApplicationPassword,
-    description: "A set of application passwords".to_string(),
+pub static ref SCHEMA_ATTR_SPONSOR_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_SPONSOR,
+    name: Attribute::Sponsor,
+    description: "The person responsible for a contractor account".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: false,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_WEBHOOK_URL_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_WEBHOOK_URL,
+    name: Attribute::WebhookUrl,
+    description: "The url that webhook events are sent to".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Url,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_WEBHOOK_SECRET_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_WEBHOOK_SECRET,
+    name: Attribute::WebhookSecret,
+    description: "The secret used to sign the events sent to a webhook".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::SecretUtf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_WEBHOOK_FILTER_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_WEBHOOK_FILTER,
+    name: Attribute::WebhookFilter,
+    description: "The entry changes that are sent to a webhook, in the form 'action:class[:attribute]'".to_string(),
 
     multivalue: true,
-    syntax: SyntaxType::ApplicationPassword,
+    syntax: SyntaxType::Utf8StringInsensitive,
     ..Default::default()
 };
 
-// === classes ===
+pub static ref SCHEMA_ATTR_CREDENTIAL_USAGE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_CREDENTIAL_USAGE,
+    name: Attribute::CredentialUsage,
+    description: "When and from where each credential of an account was last used".to_string(),
 
-pub static ref SCHEMA_CLASS_PERSON: SchemaClass = SchemaClass {
-    uuid: UUID_SCHEMA_CLASS_PERSON,
-    name: EntryClass::Person.into(),
-    description: "Object representation of a person".to_string(),
+    multivalue: true,
+    syntax: SyntaxType::CredentialUsage,
+    ..Default::default()
+};
 
-    sync_allowed: true,
-    systemmay: vec![
-        Attribute::Mail,
-        Attribute::LegalName,
-        ],
-    systemmust: vec![
-        Attribute::DisplayName,
-        Attribute::Name,
-        Attribute::IdVerificationEcKey],
+pub static ref SCHEMA_ATTR_AUTH_LOCKOUT_THRESHOLD_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_AUTH_LOCKOUT_THRESHOLD,
+    name: Attribute::AuthLockoutThreshold,
+    description: "The number of failed authentication attempts after which a credential is locked".to_string(),
+
+    syntax: SyntaxType::Uint32,
     ..Default::default()
 };
 
-pub static ref SCHEMA_CLASS_PERSON_DL5: SchemaClass = SchemaClass {
-    uuid: UUID_SCHEMA_CLASS_PERSON,
-    name: EntryClass::Person.into(),
-    description: "Object representation of a person".to_string(),
+pub static ref SCHEMA_ATTR_AUTH_LOCKOUT_WINDOW_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_AUTH_LOCKOUT_WINDOW,
+    name: Attribute::AuthLockoutWindow,
+    description: "The time in seconds over which failed authentication attempts are counted".to_string(),
 
-    sync_allowed: true,
-    systemmay: vec![
-        Attribute::PrimaryCredential,
-        Attribute::PassKeys,
-        Attribute::AttestedPasskeys,
-        Attribute::CredentialUpdateIntentToken,
-        Attribute::SshPublicKey,
-        Attribute::RadiusSecret,
-        Attribute::OAuth2ConsentScopeMap,
-        Attribute::UserAuthTokenSession,
-        Attribute::OAuth2Session,
-        Attribute::Mail,
-        Attribute::LegalName,
-    ],
-    systemmust: vec![
-        Attribute::IdVerificationEcKey
-    ],
-    systemexcludes: vec![EntryClass::ServiceAccount.into(), EntryClass::Application.into()],
+    syntax: SyntaxType::Uint32,
     ..Default::default()
 };
 
-pub static ref SCHEMA_CLASS_PERSON_DL8: SchemaClass = SchemaClass {
-    uuid: UUID_SCHEMA_CLASS_PERSON,
-    name: EntryClass::Person.into(),
-    description: "Object representation of a person".to_string(),
+pub static ref SCHEMA_ATTR_AUTH_LOCKOUT_DURATION_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_AUTH_LOCKOUT_DURATION,
+    name: Attribute::AuthLockoutDuration,
+    description: "The time in seconds that a credential is locked for once the lockout threshold is reached".to_string(),
 
-    sync_allowed: true,
-    systemmay: vec![
-        Attribute::PrimaryCredential,
-        Attribute::PassKeys,
-        Attribute::AttestedPasskeys,
-        Attribute::CredentialUpdateIntentToken,
-        Attribute::SshPublicKey,
-        Attribute::RadiusSecret,
-        Attribute::OAuth2ConsentScopeMap,
-        Attribute::UserAuthTokenSession,
-        Attribute::OAuth2Session,
-        Attribute::Mail,
-        Attribute::LegalName,
-        Attribute::ApplicationPassword,
-    ],
-    systemmust: vec![
-        Attribute::IdVerificationEcKey
-    ],
-    systemexcludes: vec![EntryClass::ServiceAccount.into(), EntryClass::Application.into()],
+    syntax: SyntaxType::Uint32,
     ..Default::default()
 };
 
-pub static ref SCHEMA_CLASS_ORGPERSON: SchemaClass = SchemaClass {
-    uuid: UUID_SCHEMA_CLASS_ORGPERSON,
-    name: EntryClass::OrgPerson.into(),
-    description: "Object representation of an org person".to_string(),
+pub static ref SCHEMA_ATTR_ACCOUNT_LIFETIME_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ACCOUNT_LIFETIME,
+    name: Attribute::AccountLifetime,
+    description: "The time in seconds after creation that member accounts without an expiry are set to expire".to_string(),
 
-    systemmay: vec![
-        Attribute::LegalName
-        ],
-    systemmust: vec![
-        Attribute::Mail,
-        Attribute::DisplayName,
-        Attribute::Name
-    ],
+    syntax: SyntaxType::Uint32,
     ..Default::default()
 };
 
-pub static ref SCHEMA_CLASS_GROUP_DL6: SchemaClass = SchemaClass {
-    uuid: UUID_SCHEMA_CLASS_GROUP,
-    name: EntryClass::Group.into(),
-    description: "Object representation of a group".to_string(),
+pub static ref SCHEMA_ATTR_ACCOUNT_ARCHIVE_DELAY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ACCOUNT_ARCHIVE_DELAY,
+    name: Attribute::AccountArchiveDelay,
+    description: "The time in seconds after expiry that member accounts are moved to the recycle bin".to_string(),
 
-    sync_allowed: true,
-    systemmay: vec![
-        Attribute::Member,
-        Attribute::GrantUiHint,
-        Attribute::Description,
-        Attribute::Mail,
-    ],
-    systemmust: vec![
-        Attribute::Name,
-        Attribute::Spn],
+    syntax: SyntaxType::Uint32,
     ..Default::default()
 };
 
-pub static ref SCHEMA_CLASS_DYNGROUP: SchemaClass = SchemaClass {
-    uuid: UUID_SCHEMA_CLASS_DYNGROUP,
-    name: EntryClass::DynGroup.into(),
-    description: "Object representation of a dynamic group".to_string(),
+pub static ref SCHEMA_ATTR_DOMAIN_THEME_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_DOMAIN_THEME,
+    name: Attribute::DomainTheme,
+    description: "The default colour theme of the web interface. One of auto, light or dark".to_string(),
 
-    systemmust: vec![Attribute::DynGroupFilter],
-    systemmay: vec![Attribute::DynMember],
-    systemsupplements: vec![Attribute::Group.into()],
+    syntax: SyntaxType::Utf8StringInsensitive,
     ..Default::default()
 };
 
-pub static ref SCHEMA_CLASS_ACCOUNT_POLICY_DL6: SchemaClass = SchemaClass {
-    uuid: UUID_SCHEMA_CLASS_ACCOUNT_POLICY,
-    name: EntryClass::AccountPolicy.into(),
-    description: "Policies applied to accounts that are members of a group".to_string(),
+pub static ref SCHEMA_ATTR_UI_THEME_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_UI_THEME,
+    name: Attribute::UiTheme,
+    description: "The colour theme of the web interface chosen by the owner of this account. One of auto, light or dark".to_string(),
 
-    systemmay: vec![
-        Attribute::AuthSessionExpiry,
-        Attribute::PrivilegeExpiry,
-        Attribute::AuthPasswordMinimumLength,
-        Attribute::CredentialTypeMinimum,
-        Attribute::WebauthnAttestationCaList,
-        Attribute::LimitSearchMaxResults,
-        Attribute::LimitSearchMaxFilterTest,
-    ],
-    systemsupplements: vec![Attribute::Group.into()],
+    syntax: SyntaxType::Utf8StringInsensitive,
     ..Default::default()
 };
 
-pub static ref SCHEMA_CLASS_ACCOUNT_POLICY_DL8: SchemaClass = SchemaClass {
-    uuid: UUID_SCHEMA_CLASS_ACCOUNT_POLICY,
-    name: EntryClass::AccountPolicy.into(),
-    description: "Policies applied to accounts that are members of a group".to_string(),
+pub static ref SCHEMA_ATTR_AUTH_PASSWORD_HISTORY_COUNT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_AUTH_PASSWORD_HISTORY_COUNT,
+    name: Attribute::AuthPasswordHistoryCount,
+    description: "The number of previous passwords that may not be reused".to_string(),
 
-    systemmay: vec![
-        Attribute::AuthSessionExpiry,
-        Attribute::PrivilegeExpiry,
-        Attribute::AuthPasswordMinimumLength,
-        Attribute::CredentialTypeMinimum,
-        Attribute::WebauthnAttestationCaList,
-        Attribute::LimitSearchMaxResults,
-        Attribute::LimitSearchMaxFilterTest,
-        Attribute::AllowPrimaryCredFallback,
-    ],
-    systemsupplements: vec![Attribute::Group.into()],
+    syntax: SyntaxType::Uint32,
     ..Default::default()
 };
 
-pub static ref SCHEMA_CLASS_ACCOUNT: SchemaClass = SchemaClass {
-    uuid: UUID_SCHEMA_CLASS_ACCOUNT,
-    name: EntryClass::Account.into(),
-    description: "Object representation of an account".to_string(),
+pub static ref SCHEMA_ATTR_ALLOW_UNIX_PASSWORD_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ALLOW_UNIX_PASSWORD,
+    name: Attribute::AllowUnixPassword,
+    description: "Allow accounts to set a POSIX password".to_string(),
 
-    sync_allowed: true,
-    systemmay: vec![
-        Attribute::PrimaryCredential,
-        Attribute::PassKeys,
+    multivalue: false,
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_SSH_PUBLICKEY_EXPIRY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_SSH_PUBLICKEY_EXPIRY,
+    name: Attribute::SshPublicKeyExpiry,
+    description: "When the ssh public keys of the object expire, in the form 'label=expiry'".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_SSH_KEY_ALLOWED_TYPE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_SSH_KEY_ALLOWED_TYPE,
+    name: Attribute::SshKeyAllowedType,
+    description: "The types of ssh public key that accounts may add, such as 'ssh-ed25519'".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_SSH_KEY_RSA_MINIMUM_BITS_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_SSH_KEY_RSA_MINIMUM_BITS,
+    name: Attribute::SshKeyRsaMinimumBits,
+    description: "The minimum length in bits of RSA ssh public keys that accounts may add".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_POSIX_DEFAULT_SHELL_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_POSIX_DEFAULT_SHELL,
+    name: Attribute::PosixDefaultShell,
+    description: "The login shell of accounts that have not set their own".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_POSIX_HOME_TEMPLATE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_POSIX_HOME_TEMPLATE,
+    name: Attribute::PosixHomeTemplate,
+    description: "A template for the name of the home directory of accounts".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_POSIX_GECOS_FORMAT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_POSIX_GECOS_FORMAT,
+    name: Attribute::PosixGecosFormat,
+    description: "A template for the gecos field of accounts".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_POSIX_HOME_SKELETON_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_POSIX_HOME_SKELETON,
+    name: Attribute::PosixHomeSkeleton,
+    description: "The name of the skeleton used to create the home directory of accounts".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_POSIX_HOME_QUOTA_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_POSIX_HOME_QUOTA,
+    name: Attribute::PosixHomeQuota,
+    description: "The filesystem quota of the home directory of an account in MiB".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HBAC_MEMBER_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HBAC_MEMBER,
+    name: Attribute::HbacMember,
+    description: "The accounts and groups that a host based access control rule applies to".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: true,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HBAC_HOST_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HBAC_HOST,
+    name: Attribute::HbacHost,
+    description: "The hosts that a host based access control rule applies to. If absent the rule applies to all hosts".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HBAC_SERVICE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HBAC_SERVICE,
+    name: Attribute::HbacService,
+    description: "The pam services that a host based access control rule applies to. If absent the rule applies to all services".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HBAC_DENY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HBAC_DENY,
+    name: Attribute::HbacDeny,
+    description: "If true, a host based access control rule denies rather than allows access".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HOST_TAG_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HOST_TAG,
+    name: Attribute::HostTag,
+    description: "Tags that group machine accounts for policy targeting. On a host group, machines with any of these tags are members of the group".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: true,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HOST_GROUP_MEMBER_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HOST_GROUP_MEMBER,
+    name: Attribute::HostGroupMember,
+    description: "The machine accounts that are members of a host group".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: true,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HBAC_HOST_GROUP_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HBAC_HOST_GROUP,
+    name: Attribute::HbacHostGroup,
+    description: "The host groups that a host based access control rule applies to, in addition to any named hosts".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: true,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HOST_JOIN_TOKEN_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HOST_JOIN_TOKEN,
+    name: Attribute::HostJoinToken,
+    description: "A one time token that allows a machine to enroll as this host".to_string(),
+
+    syntax: SyntaxType::SecretUtf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HOST_JOIN_TOKEN_EXPIRY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HOST_JOIN_TOKEN_EXPIRY,
+    name: Attribute::HostJoinTokenExpiry,
+    description: "The time after which the join token of this host is no longer valid".to_string(),
+
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_ALLOW_API_TOKENS_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ALLOW_API_TOKENS,
+    name: Attribute::AllowApiTokens,
+    description: "Allow persons to issue api tokens to themselves".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_LOGIN_HOST_TAG_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_LOGIN_HOST_TAG,
+    name: Attribute::LoginHostTag,
+    description: "The host tags of the machines that accounts subject to this policy may log in to".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_DOMAIN_LOGIN_BANNER_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_DOMAIN_LOGIN_BANNER,
+    name: Attribute::DomainLoginBanner,
+    description: "A message, such as a legal notice, that is shown on the login pages of the web interface".to_string(),
+
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_DOMAIN_PRIMARY_COLOUR_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_DOMAIN_PRIMARY_COLOUR,
+    name: Attribute::DomainPrimaryColour,
+    description: "The primary colour of the web interface, as a hex colour such as #1f6feb".to_string(),
+
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HOST_KIOSK_MODE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HOST_KIOSK_MODE,
+    name: Attribute::HostKioskMode,
+    description: "If this host is a shared workstation, where cached sessions are short and guests are cleaned up at logout".to_string(),
+
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HOST_KIOSK_CACHE_TIMEOUT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HOST_KIOSK_CACHE_TIMEOUT,
+    name: Attribute::HostKioskCacheTimeout,
+    description: "The number of seconds that a kiosk host may cache accounts for".to_string(),
+
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_HOST_KIOSK_GUEST_GROUP_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_HOST_KIOSK_GUEST_GROUP,
+    name: Attribute::HostKioskGuestGroup,
+    description: "The groups whose members are guests of a kiosk host, and have their home directories removed at logout".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MIGRATION_NAME_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MIGRATION_NAME,
+    name: Attribute::MigrationName,
+    description: "The name of a migration that was applied to the database".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MIGRATION_FROM_LEVEL_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MIGRATION_FROM_LEVEL,
+    name: Attribute::MigrationFromLevel,
+    description: "The domain level before a migration was applied".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MIGRATION_TO_LEVEL_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MIGRATION_TO_LEVEL,
+    name: Attribute::MigrationToLevel,
+    description: "The domain level after a migration was applied".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MIGRATION_APPLIED_AT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MIGRATION_APPLIED_AT,
+    name: Attribute::MigrationAppliedAt,
+    description: "The time that a migration was applied".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MIGRATION_DURATION_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MIGRATION_DURATION,
+    name: Attribute::MigrationDuration,
+    description: "The number of milliseconds that a migration took to apply".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MIGRATION_ENTRIES_CHANGED_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MIGRATION_ENTRIES_CHANGED,
+    name: Attribute::MigrationEntriesChanged,
+    description: "The number of entries that were created, modified or deleted by a migration".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_ALLOW_EMAIL_LINK_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ALLOW_EMAIL_LINK,
+    name: Attribute::AllowEmailLink,
+    description: "Allow accounts to authenticate with a link sent to their mail address".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_ANNOUNCEMENT_MESSAGE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ANNOUNCEMENT_MESSAGE,
+    name: Attribute::AnnouncementMessage,
+    description: "The message of an announcement that is shown to everyone".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_ANNOUNCEMENT_SEVERITY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ANNOUNCEMENT_SEVERITY,
+    name: Attribute::AnnouncementSeverity,
+    description: "How prominently an announcement is shown, one of 'info', 'warning' or 'critical'".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_ANNOUNCEMENT_START_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ANNOUNCEMENT_START,
+    name: Attribute::AnnouncementStart,
+    description: "The time an announcement is first shown".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_ANNOUNCEMENT_END_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ANNOUNCEMENT_END,
+    name: Attribute::AnnouncementEnd,
+    description: "The time an announcement is no longer shown".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_ANNOUNCEMENT_CLI_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ANNOUNCEMENT_CLI,
+    name: Attribute::AnnouncementCli,
+    description: "If an announcement is also shown by command line tools".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OIDC_UPSTREAM_ISSUER_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OIDC_UPSTREAM_ISSUER,
+    name: Attribute::OidcUpstreamIssuer,
+    description: "The issuer of an upstream OpenID Connect provider".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Url,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OIDC_UPSTREAM_CLIENT_ID_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OIDC_UPSTREAM_CLIENT_ID,
+    name: Attribute::OidcUpstreamClientId,
+    description: "The client id that is registered with an upstream OpenID Connect provider".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OIDC_UPSTREAM_CLIENT_SECRET_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OIDC_UPSTREAM_CLIENT_SECRET,
+    name: Attribute::OidcUpstreamClientSecret,
+    description: "The client secret that is registered with an upstream OpenID Connect provider".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::SecretUtf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OIDC_UPSTREAM_AUTHORISATION_ENDPOINT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OIDC_UPSTREAM_AUTHORISATION_ENDPOINT,
+    name: Attribute::OidcUpstreamAuthorisationEndpoint,
+    description: "Where people are sent to login with an upstream OpenID Connect provider".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Url,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OIDC_UPSTREAM_TOKEN_ENDPOINT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OIDC_UPSTREAM_TOKEN_ENDPOINT,
+    name: Attribute::OidcUpstreamTokenEndpoint,
+    description: "Where the code from an upstream OpenID Connect provider is exchanged for an id token".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Url,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OIDC_UPSTREAM_JWKS_URI_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OIDC_UPSTREAM_JWKS_URI,
+    name: Attribute::OidcUpstreamJwksUri,
+    description: "Where the keys that sign the id tokens of an upstream OpenID Connect provider are published".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Url,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_PHONE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_PHONE,
+    name: Attribute::Phone,
+    description: "The telephone numbers of a person".to_string(),
+
+    multivalue: true,
+    sync_allowed: true,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_WHITE_PAGES_TRUSTED_NETWORK_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_WHITE_PAGES_TRUSTED_NETWORK,
+    name: Attribute::WhitePagesTrustedNetwork,
+    description: "The networks, in CIDR notation, that anonymous clients may search the white pages from".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_WHITE_PAGES_RATE_LIMIT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_WHITE_PAGES_RATE_LIMIT,
+    name: Attribute::WhitePagesRateLimit,
+    description: "The number of white pages searches that an anonymous client may make from one address each minute".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_SAML2_ENTITY_ID_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_SAML2_ENTITY_ID,
+    name: Attribute::Saml2EntityId,
+    description: "The entity id of a SAML service provider".to_string(),
+
+    index: vec![IndexType::Equality],
+    unique: true,
+    multivalue: false,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_SAML2_ACS_URL_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_SAML2_ACS_URL,
+    name: Attribute::Saml2AcsUrl,
+    description: "An assertion consumer service url that a SAML service provider receives responses at".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Url,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_SAML2_GROUP_MAP_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_SAML2_GROUP_MAP,
+    name: Attribute::Saml2GroupMap,
+    description: "A reference to a group mapped to the values of the groups attribute of assertions for the associated SAML service provider".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: true,
+    syntax: SyntaxType::OauthScopeMap,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_KERBEROS_SERVICE_PRINCIPAL_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_KERBEROS_SERVICE_PRINCIPAL,
+    name: Attribute::KerberosServicePrincipal,
+    description: "The Kerberos service principal names, such as nfs/files.example.com, of the services that run on a host".to_string(),
+
+    index: vec![IndexType::Equality],
+    unique: true,
+    multivalue: true,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_SYNC_CONFLICT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_SYNC_CONFLICT,
+    name: Attribute::SyncConflict,
+    description: "The entries and attributes where the sync source disagrees with a value that Kanidm has authority over".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_JWT_SIGN_ALG_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_JWT_SIGN_ALG,
+    name: Attribute::OAuth2JwtSignAlg,
+    description: "The algorithm that tokens for an OAuth2 client are signed with, using the key object of the client".to_string(),
+
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_OAUTH2_RS_COMPUTED_CLAIM_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_OAUTH2_RS_COMPUTED_CLAIM,
+    name: Attribute::OAuth2RsComputedClaim,
+    description: "A claim whose values are computed from the attributes and group names of the account that is authorising to an OAuth2 client".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MEMBER_VALID_UNTIL_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MEMBER_VALID_UNTIL,
+    name: Attribute::MemberValidUntil,
+    description: "The time after which the membership of a member of a group lapses".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Utf8String,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MEMBERSHIP_REQUESTABLE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MEMBERSHIP_REQUESTABLE,
+    name: Attribute::MembershipRequestable,
+    description: "If accounts may request to join a group, which is approved by the entry managers of the group".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MEMBERSHIP_REQUEST_GROUP_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MEMBERSHIP_REQUEST_GROUP,
+    name: Attribute::MembershipRequestGroup,
+    description: "The group that a membership request is to join".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: false,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MEMBERSHIP_REQUESTER_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MEMBERSHIP_REQUESTER,
+    name: Attribute::MembershipRequester,
+    description: "The account that made a membership request".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: false,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MEMBERSHIP_REQUEST_STATUS_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MEMBERSHIP_REQUEST_STATUS,
+    name: Attribute::MembershipRequestStatus,
+    description: "The state of a membership request, one of 'pending', 'approved' or 'denied'".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: false,
+    syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MEMBERSHIP_REQUEST_DECIDED_BY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MEMBERSHIP_REQUEST_DECIDED_BY,
+    name: Attribute::MembershipRequestDecidedBy,
+    description: "The account that approved or denied a membership request".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_MEMBERSHIP_REQUEST_VALID_UNTIL_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_MEMBERSHIP_REQUEST_VALID_UNTIL,
+    name: Attribute::MembershipRequestValidUntil,
+    description: "The time that a membership granted by an approved membership request lapses".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_PARENT_GROUP_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_PARENT_GROUP,
+    name: Attribute::ParentGroup,
+    description: "The group that this group is nested beneath. The group and its members are also members of the parent".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: false,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_PASSWORD_BREACH_CHECK_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_PASSWORD_BREACH_CHECK,
+    name: Attribute::PasswordBreachCheck,
+    description: "Reject new passwords that are found in the dataset of breached passwords".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_AUTH_PASSWORD_MAXIMUM_AGE_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_AUTH_PASSWORD_MAXIMUM_AGE,
+    name: Attribute::AuthPasswordMaximumAge,
+    description: "The number of seconds after which a password must be changed".to_string(),
+
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_PASSWORD_LAST_CHANGED_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_PASSWORD_LAST_CHANGED,
+    name: Attribute::PasswordLastChanged,
+    description: "The time the password of the account was last changed".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::DateTime,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_AUTH_SESSION_IDLE_TIMEOUT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_AUTH_SESSION_IDLE_TIMEOUT,
+    name: Attribute::AuthSessionIdleTimeout,
+    description: "The number of seconds an authentication session may be unused before it expires".to_string(),
+
+    syntax: SyntaxType::Uint32,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_JWT_PUBLIC_KEY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_JWT_PUBLIC_KEY,
+    name: Attribute::JwtPublicKey,
+    description: "Public keys that may sign jwt assertions to authenticate the service account".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::PublicBinary,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_ACP_TARGET_GROUP_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_ACP_TARGET_GROUP,
+    name: Attribute::AcpTargetGroup,
+    description: "The groups whose members are the only entries this access control may act upon".to_string(),
+
+    index: vec![IndexType::Equality],
+    multivalue: true,
+    syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_LDAP_GROUP_COMPAT_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_LDAP_GROUP_COMPAT,
+    name: Attribute::LdapGroupCompat,
+    description: "Configuration to match nested group members in LDAP member filters".to_string(),
+
+    multivalue: false,
+    syntax: SyntaxType::Boolean,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_PASSWORD_HISTORY_DL10: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_PASSWORD_HISTORY,
+    name: Attribute::PasswordHistory,
+    description: "The previous passwords of the account, which may not be reused".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::Credential,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_CERTIFICATE_DL7: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_CERTIFICATE,
+    name: Attribute::Certificate,
+    description: "An x509 Certificate".to_string(),
+    multivalue: false,
+    syntax: SyntaxType::Certificate,
+    ..Default::default()
+};
+
+pub static ref SCHEMA_ATTR_APPLICATION_PASSWORD_DL8: SchemaAttribute = SchemaAttribute {
+    uuid: UUID_SCHEMA_ATTR_APPLICATION_PASSWORD,
+    name: Attribute::ApplicationPassword,
+    description: "A set of application passwords".to_string(),
+
+    multivalue: true,
+    syntax: SyntaxType::ApplicationPassword,
+    ..Default::default()
+};
+
+// === classes ===
+
+pub static ref SCHEMA_CLASS_PERSON: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_PERSON,
+    name: EntryClass::Person.into(),
+    description: "Object representation of a person".to_string(),
+
+    sync_allowed: true,
+    systemmay: vec![
+        Attribute::Mail,
+        Attribute::LegalName,
+        ],
+    systemmust: vec![
+        Attribute::DisplayName,
+        Attribute::Name,
+        Attribute::IdVerificationEcKey],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_PERSON_DL5: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_PERSON,
+    name: EntryClass::Person.into(),
+    description: "Object representation of a person".to_string(),
+
+    sync_allowed: true,
+    systemmay: vec![
+        Attribute::PrimaryCredential,
+        Attribute::PassKeys,
+        Attribute::AttestedPasskeys,
+        Attribute::CredentialUpdateIntentToken,
+        Attribute::SshPublicKey,
+        Attribute::RadiusSecret,
+        Attribute::OAuth2ConsentScopeMap,
+        Attribute::UserAuthTokenSession,
+        Attribute::OAuth2Session,
+        Attribute::Mail,
+        Attribute::LegalName,
+    ],
+    systemmust: vec![
+        Attribute::IdVerificationEcKey
+    ],
+    systemexcludes: vec![EntryClass::ServiceAccount.into(), EntryClass::Application.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_PERSON_DL8: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_PERSON,
+    name: EntryClass::Person.into(),
+    description: "Object representation of a person".to_string(),
+
+    sync_allowed: true,
+    systemmay: vec![
+        Attribute::PrimaryCredential,
+        Attribute::PassKeys,
+        Attribute::AttestedPasskeys,
+        Attribute::CredentialUpdateIntentToken,
+        Attribute::SshPublicKey,
+        Attribute::RadiusSecret,
+        Attribute::OAuth2ConsentScopeMap,
+        Attribute::UserAuthTokenSession,
+        Attribute::OAuth2Session,
+        Attribute::Mail,
+        Attribute::LegalName,
+        Attribute::ApplicationPassword,
+    ],
+    systemmust: vec![
+        Attribute::IdVerificationEcKey
+    ],
+    systemexcludes: vec![EntryClass::ServiceAccount.into(), EntryClass::Application.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_PERSON_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_PERSON,
+    name: EntryClass::Person.into(),
+    description: "Object representation of a person".to_string(),
+
+    sync_allowed: true,
+    systemmay: vec![
+        Attribute::PrimaryCredential,
+        Attribute::PassKeys,
+        Attribute::AttestedPasskeys,
+        Attribute::CredentialUpdateIntentToken,
+        Attribute::SshPublicKey,
+        Attribute::RadiusSecret,
+        Attribute::OAuth2ConsentScopeMap,
+        Attribute::UserAuthTokenSession,
+        Attribute::OAuth2Session,
+        Attribute::Mail,
+        Attribute::LegalName,
+        Attribute::Phone,
+        Attribute::ApplicationPassword,
+        Attribute::ApiTokenSession,
+    ],
+    systemmust: vec![
+        Attribute::IdVerificationEcKey
+    ],
+    systemexcludes: vec![EntryClass::ServiceAccount.into(), EntryClass::Application.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_CONTRACTOR_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_CONTRACTOR,
+    name: EntryClass::Contractor.into(),
+    description: "A person from outside of the organisation who is sponsored for a limited time".to_string(),
+
+    systemmust: vec![
+        Attribute::AccountExpire,
+        Attribute::Sponsor,
+    ],
+    systemsupplements: vec![EntryClass::Person.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_WEBHOOK_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_WEBHOOK,
+    name: EntryClass::Webhook.into(),
+    description: "A url that is sent signed events when entries change".to_string(),
+
+    systemmay: vec![
+        Attribute::Description,
+        Attribute::DisplayName,
+        Attribute::WebhookFilter,
+    ],
+    systemmust: vec![
+        Attribute::Name,
+        Attribute::WebhookUrl,
+        Attribute::WebhookSecret,
+    ],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_MIGRATION_RECORD_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_MIGRATION_RECORD,
+    name: EntryClass::MigrationRecord.into(),
+    description: "A record of a migration that was applied to the database".to_string(),
+
+    systemmust: vec![
+        Attribute::MigrationName,
+        Attribute::MigrationFromLevel,
+        Attribute::MigrationToLevel,
+        Attribute::MigrationAppliedAt,
+        Attribute::MigrationDuration,
+        Attribute::MigrationEntriesChanged,
+    ],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_ANNOUNCEMENT_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_ANNOUNCEMENT,
+    name: EntryClass::Announcement.into(),
+    description: "A timed message from the administrators that is shown in every view".to_string(),
+
+    systemmay: vec![
+        Attribute::Description,
+        Attribute::AnnouncementSeverity,
+        Attribute::AnnouncementStart,
+        Attribute::AnnouncementEnd,
+        Attribute::AnnouncementCli,
+    ],
+    systemmust: vec![Attribute::Name, Attribute::AnnouncementMessage],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_OIDC_UPSTREAM_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_OIDC_UPSTREAM,
+    name: EntryClass::OidcUpstream.into(),
+    description: "An upstream OpenID Connect provider that people may login with".to_string(),
+
+    systemmay: vec![Attribute::Description],
+    systemmust: vec![
+        Attribute::Name,
+        Attribute::DisplayName,
+        Attribute::OidcUpstreamIssuer,
+        Attribute::OidcUpstreamClientId,
+        Attribute::OidcUpstreamClientSecret,
+        Attribute::OidcUpstreamAuthorisationEndpoint,
+        Attribute::OidcUpstreamTokenEndpoint,
+        Attribute::OidcUpstreamJwksUri,
+    ],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_SAML2_SERVICE_PROVIDER_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_SAML2_SERVICE_PROVIDER,
+    name: EntryClass::Saml2ServiceProvider.into(),
+    description: "A SAML service provider that people may sign in to".to_string(),
+
+    systemmay: vec![
+        Attribute::Description,
+        Attribute::Saml2GroupMap,
+    ],
+    systemmust: vec![
+        Attribute::Name,
+        Attribute::DisplayName,
+        Attribute::Saml2EntityId,
+        Attribute::Saml2AcsUrl,
+    ],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_GROUP_MEMBERSHIP_REQUEST_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_GROUP_MEMBERSHIP_REQUEST,
+    name: EntryClass::GroupMembershipRequest.into(),
+    description: "A request by an account to join a group, and the decision that was made".to_string(),
+
+    // The group and requester are references that are removed if either is deleted, so they
+    // can't be required.
+    systemmay: vec![
+        Attribute::Description,
+        Attribute::MembershipRequestGroup,
+        Attribute::MembershipRequester,
+        Attribute::MembershipRequestDecidedBy,
+        Attribute::MembershipRequestValidUntil,
+    ],
+    systemmust: vec![Attribute::MembershipRequestStatus],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_HBAC_RULE_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_HBAC_RULE,
+    name: EntryClass::HbacRule.into(),
+    description: "A rule controlling which accounts may access which hosts and pam services".to_string(),
+
+    systemmay: vec![
+        Attribute::Description,
+        Attribute::HbacMember,
+        Attribute::HbacHost,
+        Attribute::HbacService,
+        Attribute::HbacDeny,
+        Attribute::HbacHostGroup,
+    ],
+    systemmust: vec![Attribute::Name],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_HOST_GROUP_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_HOST_GROUP,
+    name: EntryClass::HostGroup.into(),
+    description: "A named set of machine accounts, selected directly or by host tag, that policy can target".to_string(),
+
+    systemmay: vec![
+        Attribute::Description,
+        Attribute::HostGroupMember,
+        Attribute::HostTag,
+    ],
+    systemmust: vec![Attribute::Name],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_HOST_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_HOST,
+    name: EntryClass::Host.into(),
+    description: "A machine account that represents an enrolled unix client of the domain".to_string(),
+
+    systemmay: vec![
+        Attribute::HostJoinToken,
+        Attribute::HostJoinTokenExpiry,
+        Attribute::HostKioskMode,
+        Attribute::HostKioskCacheTimeout,
+        Attribute::HostKioskGuestGroup,
+        Attribute::KerberosServicePrincipal,
+    ],
+    systemsupplements: vec![EntryClass::ServiceAccount.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_ACCESS_CONTROL_TARGET_GROUP_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_ACCESS_CONTROL_TARGET_GROUP,
+    name: EntryClass::AccessControlTargetGroup.into(),
+    description: "System Access Control Profile Target - Group Members".to_string(),
+
+    systemmust: vec![Attribute::AcpTargetGroup],
+    systemsupplements: vec![EntryClass::AccessControlProfile.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_KEY_OBJECT_REPL_TRUST_ANCHOR_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_KEY_OBJECT_REPL_TRUST_ANCHOR,
+    name: EntryClass::KeyObjectReplTrustAnchor.into(),
+    description: "A marker class indicating that this keyobject must provide a replication trust anchor.".to_string(),
+    systemsupplements: vec![
+        EntryClass::KeyObject.into(),
+    ],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_KEY_OBJECT_SSH_CA_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_KEY_OBJECT_SSH_CA,
+    name: EntryClass::KeyObjectSshCa.into(),
+    description: "A marker class indicating that this keyobject must provide an ssh certificate authority.".to_string(),
+    systemsupplements: vec![
+        EntryClass::KeyObject.into(),
+    ],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_KEY_OBJECT_SAML2_SIGNING_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_KEY_OBJECT_SAML2_SIGNING,
+    name: EntryClass::KeyObjectSaml2Signing.into(),
+    description: "A marker class indicating that this keyobject must provide a saml2 signing key.".to_string(),
+    systemsupplements: vec![
+        EntryClass::KeyObject.into(),
+    ],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_KEY_OBJECT_KERBEROS_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_KEY_OBJECT_KERBEROS,
+    name: EntryClass::KeyObjectKerberos.into(),
+    description: "A marker class indicating that this keyobject must provide a secret that kerberos service keys are derived from.".to_string(),
+    systemsupplements: vec![
+        EntryClass::KeyObject.into(),
+    ],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_ORGPERSON: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_ORGPERSON,
+    name: EntryClass::OrgPerson.into(),
+    description: "Object representation of an org person".to_string(),
+
+    systemmay: vec![
+        Attribute::LegalName
+        ],
+    systemmust: vec![
+        Attribute::Mail,
+        Attribute::DisplayName,
+        Attribute::Name
+    ],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_GROUP_DL6: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_GROUP,
+    name: EntryClass::Group.into(),
+    description: "Object representation of a group".to_string(),
+
+    sync_allowed: true,
+    systemmay: vec![
+        Attribute::Member,
+        Attribute::MemberValidUntil,
+        Attribute::MembershipRequestable,
+        Attribute::ParentGroup,
+        Attribute::GrantUiHint,
+        Attribute::Description,
+        Attribute::Mail,
+    ],
+    systemmust: vec![
+        Attribute::Name,
+        Attribute::Spn],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_DYNGROUP: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_DYNGROUP,
+    name: EntryClass::DynGroup.into(),
+    description: "Object representation of a dynamic group".to_string(),
+
+    systemmust: vec![Attribute::DynGroupFilter],
+    systemmay: vec![Attribute::DynMember],
+    systemsupplements: vec![Attribute::Group.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_ACCOUNT_POLICY_DL6: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_ACCOUNT_POLICY,
+    name: EntryClass::AccountPolicy.into(),
+    description: "Policies applied to accounts that are members of a group".to_string(),
+
+    systemmay: vec![
+        Attribute::AuthSessionExpiry,
+        Attribute::PrivilegeExpiry,
+        Attribute::AuthPasswordMinimumLength,
+        Attribute::CredentialTypeMinimum,
+        Attribute::WebauthnAttestationCaList,
+        Attribute::LimitSearchMaxResults,
+        Attribute::LimitSearchMaxFilterTest,
+    ],
+    systemsupplements: vec![Attribute::Group.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_ACCOUNT_POLICY_DL8: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_ACCOUNT_POLICY,
+    name: EntryClass::AccountPolicy.into(),
+    description: "Policies applied to accounts that are members of a group".to_string(),
+
+    systemmay: vec![
+        Attribute::AuthSessionExpiry,
+        Attribute::PrivilegeExpiry,
+        Attribute::AuthPasswordMinimumLength,
+        Attribute::CredentialTypeMinimum,
+        Attribute::WebauthnAttestationCaList,
+        Attribute::LimitSearchMaxResults,
+        Attribute::LimitSearchMaxFilterTest,
+        Attribute::AllowPrimaryCredFallback,
+    ],
+    systemsupplements: vec![Attribute::Group.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_ACCOUNT_POLICY_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_ACCOUNT_POLICY,
+    name: EntryClass::AccountPolicy.into(),
+    description: "Policies applied to accounts that are members of a group".to_string(),
+
+    systemmay: vec![
+        Attribute::AuthSessionExpiry,
+        Attribute::PrivilegeExpiry,
+        Attribute::AuthPasswordMinimumLength,
+        Attribute::CredentialTypeMinimum,
+        Attribute::WebauthnAttestationCaList,
+        Attribute::LimitSearchMaxResults,
+        Attribute::LimitSearchMaxFilterTest,
+        Attribute::AllowPrimaryCredFallback,
+        Attribute::AuthTrustedNetwork,
+        Attribute::CredentialTypeMinimumUntrustedNetwork,
+        Attribute::AuthLockoutThreshold,
+        Attribute::AuthLockoutWindow,
+        Attribute::AuthLockoutDuration,
+        Attribute::AuthPasswordHistoryCount,
+        Attribute::AllowUnixPassword,
+        Attribute::AllowApiTokens,
+        Attribute::AllowEmailLink,
+        Attribute::PasswordBreachCheck,
+        Attribute::AuthPasswordMaximumAge,
+        Attribute::AuthSessionIdleTimeout,
+        Attribute::LoginHostTag,
+        Attribute::SshKeyAllowedType,
+        Attribute::SshKeyRsaMinimumBits,
+        Attribute::PosixDefaultShell,
+        Attribute::PosixHomeTemplate,
+        Attribute::PosixGecosFormat,
+        Attribute::PosixHomeSkeleton,
+        Attribute::AccountLifetime,
+        Attribute::AccountArchiveDelay,
+    ],
+    systemsupplements: vec![Attribute::Group.into()],
+    ..Default::default()
+};
+
+pub static ref SCHEMA_CLASS_ACCOUNT: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_ACCOUNT,
+    name: EntryClass::Account.into(),
+    description: "Object representation of an account".to_string(),
+
+    sync_allowed: true,
+    systemmay: vec![
+        Attribute::PrimaryCredential,
+        Attribute::PassKeys,
         Attribute::AttestedPasskeys,
         Attribute::CredentialUpdateIntentToken,
         Attribute::SshPublicKey,
@@ -1056,6 +2316,36 @@ pub static ref SCHEMA_CLASS_ACCOUNT_DL5: SchemaClass = SchemaClass {
     ..Default::default()
 };
 
+pub static ref SCHEMA_CLASS_ACCOUNT_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_ACCOUNT,
+    name: EntryClass::Account.into(),
+    description: "Object representation of an account".to_string(),
+
+    sync_allowed: true,
+    systemmay: vec![
+        Attribute::AccountExpire,
+        Attribute::AccountValidFrom,
+        Attribute::NameHistory,
+        Attribute::CredentialUsage,
+        Attribute::PasswordHistory,
+        Attribute::PasswordLastChanged,
+        Attribute::SshPublicKeyExpiry,
+        Attribute::NotificationOptOut,
+        Attribute::UiTheme,
+    ],
+    systemmust: vec![
+        Attribute::DisplayName,
+        Attribute::Name,
+        Attribute::Spn
+    ],
+    systemsupplements: vec![
+        EntryClass::Person.into(),
+        EntryClass::ServiceAccount.into(),
+        EntryClass::OAuth2ResourceServer.into(),
+    ],
+    ..Default::default()
+};
+
 pub static ref SCHEMA_CLASS_SERVICE_ACCOUNT_DL6: SchemaClass = SchemaClass {
     uuid: UUID_SCHEMA_CLASS_SERVICE_ACCOUNT,
     name: EntryClass::ServiceAccount.into(),
@@ -1100,6 +2390,29 @@ pub static ref SCHEMA_CLASS_SERVICE_ACCOUNT_DL7: SchemaClass = SchemaClass {
     ..Default::default()
 };
 
+pub static ref SCHEMA_CLASS_SERVICE_ACCOUNT_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_SERVICE_ACCOUNT,
+    name: EntryClass::ServiceAccount.into(),
+    description: "Object representation of service account".to_string(),
+
+    sync_allowed: true,
+    systemmay: vec![
+        Attribute::SshPublicKey,
+        Attribute::UserAuthTokenSession,
+        Attribute::OAuth2Session,
+        Attribute::OAuth2ConsentScopeMap,
+        Attribute::Description,
+
+        Attribute::Mail,
+        Attribute::PrimaryCredential,
+        Attribute::ApiTokenSession,
+        Attribute::HostTag,
+        Attribute::JwtPublicKey,
+    ],
+    systemexcludes: vec![EntryClass::Person.into()],
+    ..Default::default()
+};
+
 pub static ref SCHEMA_CLASS_SYNC_ACCOUNT_DL6: SchemaClass = SchemaClass {
     uuid: UUID_SCHEMA_CLASS_SYNC_ACCOUNT,
     name: EntryClass::SyncAccount.into(),
@@ -1133,6 +2446,23 @@ pub static ref SCHEMA_CLASS_SYNC_ACCOUNT_DL7: SchemaClass = SchemaClass {
     ..Default::default()
 };
 
+pub static ref SCHEMA_CLASS_SYNC_ACCOUNT_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_SYNC_ACCOUNT,
+    name: EntryClass::SyncAccount.into(),
+    description: "Object representation of sync account".to_string(),
+
+    systemmust: vec![Attribute::Name],
+    systemmay: vec![
+        Attribute::SyncTokenSession,
+        Attribute::SyncCookie,
+        Attribute::SyncCredentialPortal,
+        Attribute::SyncYieldAuthority,
+        Attribute::SyncConflict,
+    ],
+    systemexcludes: vec![EntryClass::Account.into()],
+    ..Default::default()
+};
+
 pub static ref SCHEMA_CLASS_DOMAIN_INFO_DL6: SchemaClass = SchemaClass {
     uuid: UUID_SCHEMA_CLASS_DOMAIN_INFO,
     name: EntryClass::DomainInfo.into(),
@@ -1237,11 +2567,17 @@ pub static ref SCHEMA_CLASS_DOMAIN_INFO_DL10: SchemaClass = SchemaClass {
         Attribute::DomainLdapBasedn,
         Attribute::LdapMaxQueryableAttrs,
         Attribute::LdapAllowUnixPwBind,
+        Attribute::LdapGroupCompat,
         Attribute::Image,
         Attribute::PatchLevel,
         Attribute::DomainDevelopmentTaint,
         Attribute::DomainAllowEasterEggs,
         Attribute::DomainDisplayName,
+        Attribute::DomainTheme,
+        Attribute::DomainLoginBanner,
+        Attribute::DomainPrimaryColour,
+        Attribute::WhitePagesTrustedNetwork,
+        Attribute::WhitePagesRateLimit,
     ],
     systemmust: vec![
         Attribute::Name,
@@ -1269,7 +2605,11 @@ pub static ref SCHEMA_CLASS_POSIXACCOUNT: SchemaClass = SchemaClass {
     description: "Object representation of a posix account, requires account".to_string(),
 
     sync_allowed: true,
-    systemmay: vec![Attribute::LoginShell, Attribute::UnixPassword],
+    systemmay: vec![
+        Attribute::LoginShell,
+        Attribute::UnixPassword,
+        Attribute::PosixHomeQuota,
+    ],
     systemmust: vec![Attribute::GidNumber],
     systemsupplements: vec![Attribute::Account.into()],
     ..Default::default()
@@ -1285,7 +2625,8 @@ pub static ref SCHEMA_CLASS_SYSTEM_CONFIG: SchemaClass = SchemaClass {
         Attribute::BadlistPassword,
         Attribute::AuthSessionExpiry,
         Attribute::PrivilegeExpiry,
-        Attribute::DeniedName
+        Attribute::DeniedName,
+        Attribute::DeniedPasswordTerm
         ],
     ..Default::default()
 };
@@ -1394,6 +2735,43 @@ pub static ref SCHEMA_CLASS_OAUTH2_RS_DL9: SchemaClass = SchemaClass {
     ..Default::default()
 };
 
+pub static ref SCHEMA_CLASS_OAUTH2_RS_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_OAUTH2_RS,
+    name: EntryClass::OAuth2ResourceServer.into(),
+    description: "The class representing a configured OAuth2 Client".to_string(),
+
+    systemmay: vec![
+        Attribute::Description,
+        Attribute::OAuth2RsScopeMap,
+        Attribute::OAuth2RsSupScopeMap,
+        Attribute::Rs256PrivateKeyDer,
+        Attribute::OAuth2JwtLegacyCryptoEnable,
+        Attribute::OAuth2JwtSignAlg,
+        Attribute::OAuth2PreferShortUsername,
+        Attribute::Image,
+        Attribute::OAuth2RsClaimMap,
+        Attribute::OAuth2Session,
+        Attribute::OAuth2RsOrigin,
+        Attribute::OAuth2StrictRedirectUri,
+        Attribute::OAuth2DeviceFlowEnable,
+        Attribute::EntryManagedBy,
+        Attribute::OAuth2TokenExchangeAudience,
+        Attribute::OAuth2AllowInsecureRefreshTokenReuse,
+        Attribute::OAuth2RedirectUriMode,
+        Attribute::OAuth2AllowedGrantTypes,
+        Attribute::OAuth2AllowedResponseTypes,
+        Attribute::OAuth2RsScopeDescription,
+        Attribute::OAuth2RsComputedClaim,
+        Attribute::OAuth2RsAssignedMember,
+    ],
+    systemmust: vec![
+        Attribute::OAuth2RsOriginLanding,
+        Attribute::OAuth2RsTokenKey,
+        Attribute::Es256PrivateKeyDer,
+    ],
+    ..Default::default()
+};
+
 pub static ref SCHEMA_CLASS_OAUTH2_RS_BASIC_DL5: SchemaClass = SchemaClass {
     uuid: UUID_SCHEMA_CLASS_OAUTH2_RS_BASIC,
     name: EntryClass::OAuth2ResourceServerBasic.into(),
@@ -1401,6 +2779,11 @@ pub static ref SCHEMA_CLASS_OAUTH2_RS_BASIC_DL5: SchemaClass = SchemaClass {
 
     systemmay: vec![
         Attribute::OAuth2AllowInsecureClientDisablePkce,
+        Attribute::OAuth2RsBasicSecretExpiry,
+        Attribute::OAuth2RsBasicSecretLastUsed,
+        Attribute::OAuth2RsBasicSecretPrevious,
+        Attribute::OAuth2RsBasicSecretPreviousExpiry,
+        Attribute::OAuth2RsBasicSecretPreviousLastUsed,
     ],
     systemmust: vec![ Attribute::OAuth2RsBasicSecret],
     systemexcludes: vec![ EntryClass::OAuth2ResourceServerPublic.into()],
@@ -1418,6 +2801,15 @@ pub static ref SCHEMA_CLASS_OAUTH2_RS_PUBLIC_DL4: SchemaClass = SchemaClass {
     ..Default::default()
 };
 
+pub static ref SCHEMA_CLASS_OAUTH2_RS_NATIVE_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_OAUTH2_RS_NATIVE,
+    name: EntryClass::OAuth2ResourceServerNative.into(),
+    description: "The class representing a configured Public OAuth2 Client for a native application".to_string(),
+
+    systemsupplements: vec![EntryClass::OAuth2ResourceServerPublic.into()],
+    ..Default::default()
+};
+
 // =========================================
 // KeyProviders
 
@@ -1467,6 +2859,16 @@ pub static ref SCHEMA_CLASS_KEY_OBJECT_JWT_ES256_DL6: SchemaClass = SchemaClass
     ..Default::default()
 };
 
+pub static ref SCHEMA_CLASS_KEY_OBJECT_JWT_RS256_DL10: SchemaClass = SchemaClass {
+    uuid: UUID_SCHEMA_CLASS_KEY_OBJECT_JWT_RS256,
+    name: EntryClass::KeyObjectJwtRs256.into(),
+    description: "A marker class indicating that this keyobject must provide jwt rs256 capability.".to_string(),
+    systemsupplements: vec![
+        EntryClass::KeyObject.into(),
+    ],
+    ..Default::default()
+};
+
 pub static ref SCHEMA_CLASS_KEY_OBJECT_JWE_A128GCM_DL6: SchemaClass = SchemaClass {
     uuid: UUID_SCHEMA_CLASS_KEY_OBJECT_JWE_A128GCM,
     name: EntryClass::KeyObjectJweA128GCM.into(),
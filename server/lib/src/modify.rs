@@ -54,6 +54,17 @@ pub fn m_assert(attr: Attribute, v: &PartialValue) -> Modify {
 }
 
 impl Modify {
+    /// The attribute that this modification applies to.
+    pub fn attr(&self) -> &Attribute {
+        match self {
+            Modify::Present(attr, _)
+            | Modify::Removed(attr, _)
+            | Modify::Purged(attr)
+            | Modify::Assert(attr, _)
+            | Modify::Set(attr, _) => attr,
+        }
+    }
+
     pub fn from(
         m: &ProtoModify,
         qs: &mut QueryServerWriteTransaction,
@@ -268,6 +279,21 @@ impl ModifyList<ModifyValid> {
     pub fn iter(&self) -> slice::Iter<Modify> {
         self.mods.iter()
     }
+
+    /// True if this modlist only removes sessions, such as when logging out.
+    pub(crate) fn only_revokes_sessions(&self) -> bool {
+        self.mods.iter().all(|m| {
+            matches!(
+                m,
+                Modify::Removed(
+                    Attribute::UserAuthTokenSession
+                        | Attribute::OAuth2Session
+                        | Attribute::ApiTokenSession,
+                    _
+                )
+            )
+        })
+    }
 }
 
 impl<VALID> ModifyList<VALID> {
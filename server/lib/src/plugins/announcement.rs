@@ -0,0 +1,133 @@
+//! Announcements are shown to every user of the instance, so check that their severity is
+//! one we know how to display and that they do not end before they start.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use kanidm_proto::internal::AnnouncementSeverity;
+
+use crate::plugins::Plugin;
+use crate::prelude::*;
+
+pub struct Announcement {}
+
+impl Plugin for Announcement {
+    fn id() -> &'static str {
+        "plugin_announcement"
+    }
+
+    #[instrument(level = "debug", name = "announcement_pre_create_transform", skip_all)]
+    fn pre_create_transform(
+        _qs: &mut QueryServerWriteTransaction,
+        cand: &mut Vec<Entry<EntryInvalid, EntryNew>>,
+        _ce: &CreateEvent,
+    ) -> Result<(), OperationError> {
+        Self::modify_inner(cand)
+    }
+
+    #[instrument(level = "debug", name = "announcement_pre_modify", skip_all)]
+    fn pre_modify(
+        _qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &ModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::modify_inner(cand)
+    }
+
+    #[instrument(level = "debug", name = "announcement_pre_batch_modify", skip_all)]
+    fn pre_batch_modify(
+        _qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::modify_inner(cand)
+    }
+}
+
+impl Announcement {
+    fn modify_inner<T: Clone>(cand: &mut [Entry<EntryInvalid, T>]) -> Result<(), OperationError> {
+        cand.iter()
+            .filter(|e| e.attribute_equality(Attribute::Class, &EntryClass::Announcement.into()))
+            .try_for_each(|e| {
+                if let Some(severity) = e.get_ava_single_iutf8(Attribute::AnnouncementSeverity) {
+                    if AnnouncementSeverity::from_str(severity).is_err() {
+                        error!(?severity, "invalid announcement severity");
+                        return Err(OperationError::InvalidAttribute(format!(
+                            "Invalid announcement severity {severity}"
+                        )));
+                    }
+                }
+
+                let start = e.get_ava_single_datetime(Attribute::AnnouncementStart);
+                let end = e.get_ava_single_datetime(Attribute::AnnouncementEnd);
+
+                if let (Some(start), Some(end)) = (start, end) {
+                    if end <= start {
+                        error!(?start, ?end, "announcement ends before it starts");
+                        return Err(OperationError::InvalidAttribute(
+                            "Announcement must end after it starts".to_string(),
+                        ));
+                    }
+                }
+
+                Ok(())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    fn announcement(name: &str, uuid: Uuid, severity: &str) -> EntryInitNew {
+        entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Announcement.to_value()),
+            (Attribute::Name, Value::new_iname(name)),
+            (Attribute::Uuid, Value::Uuid(uuid)),
+            (
+                Attribute::AnnouncementMessage,
+                Value::new_utf8s("Beware of phishing emails")
+            ),
+            (Attribute::AnnouncementSeverity, Value::new_iutf8(severity))
+        )
+    }
+
+    #[qs_test]
+    async fn test_announcement_validation(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let mut server_txn = server.write(ct).await.unwrap();
+
+        assert!(server_txn
+            .internal_create(vec![announcement("announcement_a", Uuid::new_v4(), "dire")])
+            .is_err());
+
+        let uuid = Uuid::new_v4();
+        assert!(server_txn
+            .internal_create(vec![announcement("announcement_b", uuid, "critical")])
+            .is_ok());
+
+        // An announcement can not end before it starts.
+        let modlist = ModifyList::new_list(vec![
+            Modify::Present(
+                Attribute::AnnouncementStart,
+                Value::new_datetime_epoch(ct + Duration::from_secs(60)),
+            ),
+            Modify::Present(Attribute::AnnouncementEnd, Value::new_datetime_epoch(ct)),
+        ]);
+        assert!(server_txn.internal_modify_uuid(uuid, &modlist).is_err());
+
+        let modlist = ModifyList::new_list(vec![
+            Modify::Present(Attribute::AnnouncementStart, Value::new_datetime_epoch(ct)),
+            Modify::Present(
+                Attribute::AnnouncementEnd,
+                Value::new_datetime_epoch(ct + Duration::from_secs(60)),
+            ),
+        ]);
+        assert!(server_txn.internal_modify_uuid(uuid, &modlist).is_ok());
+
+        assert!(server_txn.commit().is_ok());
+    }
+}
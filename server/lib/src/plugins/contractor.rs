@@ -0,0 +1,265 @@
+//! Contractors are people from outside of the organisation who are given an account for a
+//! limited time. Schema requires that they have an expiry and a sponsor, and this plugin
+//! asserts that the sponsor is a person who is accountable for them. Once the expiry passes
+//! the account is no longer valid, so a lapsed contractor is unable to authenticate and
+//! their existing sessions are rejected.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::plugins::Plugin;
+use crate::prelude::*;
+
+pub struct Contractor {}
+
+impl Plugin for Contractor {
+    fn id() -> &'static str {
+        "plugin_contractor"
+    }
+
+    #[instrument(level = "debug", name = "contractor_pre_create_transform", skip_all)]
+    fn pre_create_transform(
+        qs: &mut QueryServerWriteTransaction,
+        cand: &mut Vec<Entry<EntryInvalid, EntryNew>>,
+        _ce: &CreateEvent,
+    ) -> Result<(), OperationError> {
+        Self::check_sponsors(qs, cand.iter())
+    }
+
+    #[instrument(level = "debug", name = "contractor_pre_modify", skip_all)]
+    fn pre_modify(
+        qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &ModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::check_sponsors(qs, cand.iter())
+    }
+
+    #[instrument(level = "debug", name = "contractor_pre_batch_modify", skip_all)]
+    fn pre_batch_modify(
+        qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::check_sponsors(qs, cand.iter())
+    }
+
+    #[instrument(level = "debug", name = "contractor_verify", skip_all)]
+    fn verify(qs: &mut QueryServerReadTransaction) -> Vec<Result<(), ConsistencyError>> {
+        let filt = filter!(f_eq(Attribute::Class, EntryClass::Contractor.into()));
+
+        let contractors = match qs.internal_search(filt) {
+            Ok(entries) => entries,
+            Err(err) => {
+                error!(?err);
+                return vec![Err(ConsistencyError::QueryServerSearchFailure)];
+            }
+        };
+
+        contractors
+            .iter()
+            .filter_map(|entry| {
+                let valid = entry
+                    .get_ava_single_refer(Attribute::Sponsor)
+                    .and_then(|sponsor| qs.internal_search_uuid(sponsor).ok())
+                    .map(|sponsor| Self::is_valid_sponsor(entry.get_uuid(), sponsor.as_ref()))
+                    .unwrap_or(false);
+
+                (!valid).then(|| {
+                    Err(ConsistencyError::InvalidAttributeType(format!(
+                        "{} has an invalid {}",
+                        entry.get_uuid(),
+                        Attribute::Sponsor
+                    )))
+                })
+            })
+            .collect()
+    }
+}
+
+impl Contractor {
+    fn is_valid_sponsor(contractor: Uuid, sponsor: &EntrySealedCommitted) -> bool {
+        sponsor.get_uuid() != contractor
+            && sponsor.attribute_equality(Attribute::Class, &EntryClass::Person.into())
+            && !sponsor.attribute_equality(Attribute::Class, &EntryClass::Contractor.into())
+    }
+
+    fn check_sponsors<'a, STATE: 'a>(
+        qs: &mut QueryServerWriteTransaction,
+        cand: impl Iterator<Item = &'a Entry<EntryInvalid, STATE>>,
+    ) -> Result<(), OperationError> {
+        // Schema asserts the sponsor is present, we only need to check who it refers to.
+        let sponsors: Vec<(Uuid, Uuid)> = cand
+            .filter(|entry| {
+                entry.attribute_equality(Attribute::Class, &EntryClass::Contractor.into())
+            })
+            .filter_map(|entry| {
+                let uuid = entry.get_uuid()?;
+                let sponsor = entry.get_ava_single_refer(Attribute::Sponsor)?;
+                Some((uuid, sponsor))
+            })
+            .collect();
+
+        if sponsors.is_empty() {
+            return Ok(());
+        }
+
+        let contractors: BTreeSet<Uuid> = sponsors.iter().map(|(uuid, _)| *uuid).collect();
+
+        for (uuid, sponsor) in sponsors {
+            // A contractor can't sponsor another, even one being created in the same operation.
+            let valid = !contractors.contains(&sponsor)
+                && qs
+                    .internal_search_uuid(sponsor)
+                    .map(|sponsor| Self::is_valid_sponsor(uuid, sponsor.as_ref()))
+                    .unwrap_or(false);
+
+            if !valid {
+                error!(?uuid, ?sponsor, "contractor sponsor is not a valid person");
+                return Err(OperationError::PL0002ContractorSponsorInvalid);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    const SPONSOR_UUID: Uuid = uuid!("b3bf3ad9-1d5c-4b0c-8b3a-52d6e6a3a4a1");
+
+    fn person(name: &str, uuid: Uuid) -> EntryInitNew {
+        entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname(name)),
+            (Attribute::Uuid, Value::Uuid(uuid)),
+            (Attribute::DisplayName, Value::new_utf8s(name))
+        )
+    }
+
+    fn contractor(name: &str, uuid: Uuid, sponsor: Uuid) -> EntryInitNew {
+        let mut e = person(name, uuid);
+        e.add_ava(Attribute::Class, EntryClass::Contractor.to_value());
+        e.add_ava(Attribute::Sponsor, Value::Refer(sponsor));
+        e.add_ava(
+            Attribute::AccountExpire,
+            Value::new_datetime_epoch(Duration::from_secs(TEST_CURRENT_TIME + 86400)),
+        );
+        e
+    }
+
+    #[qs_test]
+    async fn test_contractor_create(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        assert!(server_txn
+            .internal_create(vec![person("sponsor", SPONSOR_UUID)])
+            .is_ok());
+
+        // A contractor must have an expiry and a sponsor.
+        let mut no_expiry = contractor("contractor_a", Uuid::new_v4(), SPONSOR_UUID);
+        no_expiry.remove_ava(&Attribute::AccountExpire);
+        assert!(server_txn.internal_create(vec![no_expiry]).is_err());
+
+        let mut no_sponsor = contractor("contractor_b", Uuid::new_v4(), SPONSOR_UUID);
+        no_sponsor.remove_ava(&Attribute::Sponsor);
+        assert!(server_txn.internal_create(vec![no_sponsor]).is_err());
+
+        // The sponsor must be a person.
+        assert_eq!(
+            server_txn.internal_create(vec![contractor(
+                "contractor_c",
+                Uuid::new_v4(),
+                UUID_IDM_ADMINS
+            )]),
+            Err(OperationError::PL0002ContractorSponsorInvalid)
+        );
+
+        // Contractors can't sponsor themselves or each other.
+        let c_uuid = Uuid::new_v4();
+        assert_eq!(
+            server_txn.internal_create(vec![contractor("contractor_d", c_uuid, c_uuid)]),
+            Err(OperationError::PL0002ContractorSponsorInvalid)
+        );
+
+        assert!(server_txn
+            .internal_create(vec![contractor("contractor_e", c_uuid, SPONSOR_UUID)])
+            .is_ok());
+
+        assert_eq!(
+            server_txn.internal_create(vec![contractor("contractor_f", Uuid::new_v4(), c_uuid)]),
+            Err(OperationError::PL0002ContractorSponsorInvalid)
+        );
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_contractor_modify(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let p_uuid = Uuid::new_v4();
+        assert!(server_txn
+            .internal_create(vec![
+                person("sponsor", SPONSOR_UUID),
+                person("person", p_uuid)
+            ])
+            .is_ok());
+
+        // A person can't become a contractor without a sponsor.
+        assert!(server_txn
+            .internal_modify_uuid(
+                p_uuid,
+                &ModifyList::new_append(Attribute::Class, EntryClass::Contractor.to_value())
+            )
+            .is_err());
+
+        assert!(server_txn
+            .internal_modify_uuid(
+                p_uuid,
+                &ModifyList::new_list(vec![
+                    Modify::Present(Attribute::Class, EntryClass::Contractor.to_value()),
+                    Modify::Present(Attribute::Sponsor, Value::Refer(SPONSOR_UUID)),
+                    Modify::Present(
+                        Attribute::AccountExpire,
+                        Value::new_datetime_epoch(Duration::from_secs(TEST_CURRENT_TIME))
+                    ),
+                ])
+            )
+            .is_ok());
+
+        // The sponsor can't be changed to a group.
+        assert_eq!(
+            server_txn.internal_modify_uuid(
+                p_uuid,
+                &ModifyList::new_purge_and_set(Attribute::Sponsor, Value::Refer(UUID_IDM_ADMINS))
+            ),
+            Err(OperationError::PL0002ContractorSponsorInvalid)
+        );
+
+        // The expiry can be extended, but not removed.
+        assert!(server_txn
+            .internal_modify_uuid(
+                p_uuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::AccountExpire,
+                    Value::new_datetime_epoch(Duration::from_secs(TEST_CURRENT_TIME + 86400))
+                )
+            )
+            .is_ok());
+
+        assert!(server_txn
+            .internal_modify_uuid(p_uuid, &ModifyList::new_purge(Attribute::AccountExpire))
+            .is_err());
+
+        assert!(server_txn.commit().is_ok());
+    }
+}
@@ -3,8 +3,12 @@ use std::convert::TryFrom;
 use std::iter::once;
 use std::sync::Arc;
 
+use kanidm_proto::internal::PasswordFeedback;
+
+use crate::credential::history::{password_history_contains, password_history_values};
 use crate::credential::{Credential, Password};
 use crate::event::{CreateEvent, ModifyEvent};
+use crate::idm::group::load_account_policy;
 use crate::plugins::Plugin;
 use crate::prelude::*;
 
@@ -30,26 +34,91 @@ impl Plugin for CredImport {
 
     #[instrument(level = "debug", name = "password_import_pre_modify", skip_all)]
     fn pre_modify(
-        _qs: &mut QueryServerWriteTransaction,
-        _pre_cand: &[Arc<EntrySealedCommitted>],
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
         cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
         _me: &ModifyEvent,
     ) -> Result<(), OperationError> {
-        Self::modify_inner(cand)
+        let imported = Self::password_imported(cand);
+        Self::modify_inner(cand)?;
+        Self::password_history_inner(qs, pre_cand, cand, &imported)
     }
 
     #[instrument(level = "debug", name = "password_import_pre_batch_modify", skip_all)]
     fn pre_batch_modify(
-        _qs: &mut QueryServerWriteTransaction,
-        _pre_cand: &[Arc<EntrySealedCommitted>],
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
         cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
         _me: &BatchModifyEvent,
     ) -> Result<(), OperationError> {
-        Self::modify_inner(cand)
+        let imported = Self::password_imported(cand);
+        Self::modify_inner(cand)?;
+        Self::password_history_inner(qs, pre_cand, cand, &imported)
     }
 }
 
 impl CredImport {
+    fn password_imported<T>(cand: &[Entry<EntryInvalid, T>]) -> Vec<bool> {
+        cand.iter()
+            .map(|e| e.attribute_pres(Attribute::PasswordImport))
+            .collect()
+    }
+
+    // An imported password can only be compared to the history by its hash, so reuse is
+    // only detected when the hash is in the same format with the same salt.
+    fn password_history_inner(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut [Entry<EntryInvalid, EntryCommitted>],
+        imported: &[bool],
+    ) -> Result<(), OperationError> {
+        for ((pre, post), _) in pre_cand
+            .iter()
+            .zip(cand.iter_mut())
+            .zip(imported)
+            .filter(|(_, imported)| **imported)
+        {
+            let Some(prev_pw) = pre
+                .get_ava_single_credential(Attribute::PrimaryCredential)
+                .and_then(|c| c.password_ref().ok())
+            else {
+                continue;
+            };
+
+            let Some(next_pw) = post
+                .get_ava_single_credential(Attribute::PrimaryCredential)
+                .and_then(|c| c.password_ref().ok())
+                .cloned()
+            else {
+                continue;
+            };
+
+            // Importing the same hash again, such as during a sync, isn't a change.
+            if prev_pw == &next_pw {
+                continue;
+            }
+
+            let pw_history_count = load_account_policy(pre, qs)?.pw_history_count();
+            if pw_history_count == 0 {
+                continue;
+            }
+
+            let history = post.get_ava_as_credential_map(Attribute::PasswordHistory);
+
+            if password_history_contains(history, pw_history_count, &next_pw) {
+                error!(entry_id = %post.get_display_id(), "{} matches a previous password", Attribute::PasswordImport);
+                return Err(OperationError::PasswordQuality(vec![
+                    PasswordFeedback::DontReusePasswords,
+                ]));
+            }
+
+            let values = password_history_values(history, pw_history_count, prev_pw);
+            post.set_ava(&Attribute::PasswordHistory, values);
+        }
+
+        Ok(())
+    }
+
     fn modify_inner<T: Clone>(cand: &mut [Entry<EntryInvalid, T>]) -> Result<(), OperationError> {
         cand.iter_mut().try_for_each(|e| {
             // PASSWORD IMPORT
@@ -169,10 +238,12 @@ mod tests {
     use crate::credential::{Credential, CredentialType};
     use crate::prelude::*;
     use kanidm_lib_crypto::CryptoPolicy;
+    use kanidm_proto::internal::PasswordFeedback;
 
     const IMPORT_HASH: &str =
         "pbkdf2_sha256$36000$xIEozuZVAoYm$uW1b35DUKyhvQAf1mBqMvoBDcqSD06juzyO/nmyV0+w=";
     // const IMPORT_PASSWORD: &'static str = "eicieY7ahchaoCh0eeTa";
+    const IMPORT_HASH_SHA512: &str = "{SHA512}sQnzu7wkTrgkQZF+0G1hi5AI3Qmzvv0bXgc5THBqi7mAsdd4Xll27ASbRt9fEyavWi6m0QP9B8lThf+rDKy8hg==";
 
     #[test]
     fn test_pre_create_password_import_1() {
@@ -497,4 +568,66 @@ mod tests {
             }
         );
     }
+
+    #[qs_test]
+    async fn test_modify_password_import_history(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        assert!(server_txn
+            .internal_modify_uuid(
+                UUID_IDM_ALL_ACCOUNTS,
+                &ModifyList::new_purge_and_set(
+                    Attribute::AuthPasswordHistoryCount,
+                    Value::Uint32(2)
+                )
+            )
+            .is_ok());
+
+        let t_uuid = Uuid::new_v4();
+        assert!(server_txn
+            .internal_create(vec![entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Name, Value::new_iname("testperson")),
+                (Attribute::Uuid, Value::Uuid(t_uuid)),
+                (Attribute::DisplayName, Value::new_utf8s("testperson")),
+                (Attribute::PasswordImport, Value::from(IMPORT_HASH))
+            )])
+            .is_ok());
+
+        let import = |hash: &str| {
+            ModifyList::new_list(vec![Modify::Present(
+                Attribute::PasswordImport,
+                Value::from(hash),
+            )])
+        };
+
+        // Importing the same hash again isn't a change to the password.
+        assert!(server_txn
+            .internal_modify_uuid(t_uuid, &import(IMPORT_HASH))
+            .is_ok());
+        assert!(server_txn
+            .internal_modify_uuid(t_uuid, &import(IMPORT_HASH_SHA512))
+            .is_ok());
+
+        let e = server_txn
+            .internal_search_uuid(t_uuid)
+            .expect("failed to get entry");
+        assert_eq!(
+            e.get_ava_as_credential_map(Attribute::PasswordHistory)
+                .map(|history| history.len()),
+            Some(1)
+        );
+
+        // The previous hash is now in the history, and can't be imported again.
+        assert_eq!(
+            server_txn.internal_modify_uuid(t_uuid, &import(IMPORT_HASH)),
+            Err(OperationError::PasswordQuality(vec![
+                PasswordFeedback::DontReusePasswords
+            ]))
+        );
+
+        assert!(server_txn.commit().is_ok());
+    }
 }
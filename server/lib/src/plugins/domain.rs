@@ -20,6 +20,10 @@ lazy_static! {
         Regex::new(r"^(dc|o|ou)=[a-z][a-z0-9]*(,(dc|o|ou)=[a-z][a-z0-9]*)*$")
             .expect("Invalid domain ldap basedn regex")
     };
+    pub static ref DOMAIN_PRIMARY_COLOUR_RE: Regex = {
+        #[allow(clippy::expect_used)]
+        Regex::new(r"^#[0-9a-f]{6}$").expect("Invalid domain primary colour regex")
+    };
 }
 
 pub struct Domain {}
@@ -82,6 +86,20 @@ impl Domain {
                     }
                 }
 
+                // The primary colour is rendered into the stylesheet of the web interface, so
+                // it must only ever be a plain hex colour.
+                if let Some(colour) = e.get_ava_single_iutf8(Attribute::DomainPrimaryColour) {
+                    if !DOMAIN_PRIMARY_COLOUR_RE.is_match(colour) {
+                        error!(
+                            "Invalid {} '{}'. Must pass regex \"{}\"",
+                            Attribute::DomainPrimaryColour,
+                            colour,
+                            *DOMAIN_PRIMARY_COLOUR_RE
+                        );
+                        return Err(OperationError::InvalidState);
+                    }
+                }
+
                 // We always set this, because the DB uuid is authoritative.
                 let u = Value::Uuid(qs.get_domain_uuid());
                 e.set_ava(&Attribute::DomainUuid, once(u));
@@ -147,4 +165,46 @@ mod tests {
 
         assert!(e_dom.attribute_equality(Attribute::DomainUuid, &PartialValue::Uuid(u_dom)));
     }
+
+    #[qs_test]
+    async fn test_domain_branding(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        // Anything that isn't a hex colour is rejected.
+        for colour in ["blue", "#12345", "#1f6feb; }", "#12345g"] {
+            assert_eq!(
+                server_txn.internal_modify_uuid(
+                    UUID_DOMAIN_INFO,
+                    &ModifyList::new_purge_and_set(
+                        Attribute::DomainPrimaryColour,
+                        Value::new_iutf8(colour)
+                    ),
+                ),
+                Err(OperationError::InvalidState)
+            );
+        }
+
+        assert!(server_txn
+            .internal_modify_uuid(
+                UUID_DOMAIN_INFO,
+                &ModifyList::new_list(vec![
+                    Modify::Purged(Attribute::DomainPrimaryColour),
+                    Modify::Present(Attribute::DomainPrimaryColour, Value::new_iutf8("#1F6FEB")),
+                    Modify::Purged(Attribute::DomainLoginBanner),
+                    Modify::Present(
+                        Attribute::DomainLoginBanner,
+                        Value::new_utf8s("Authorised use only.")
+                    ),
+                ]),
+            )
+            .is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let server_txn = server.read().await.unwrap();
+        assert_eq!(server_txn.d_info.primary_colour(), Some("#1f6feb"));
+        assert_eq!(
+            server_txn.d_info.login_banner(),
+            Some("Authorised use only.")
+        );
+    }
 }
@@ -1,4 +1,6 @@
 use compact_jwt::{crypto::JwsRs256Signer, JwsEs256Signer};
+use kanidm_proto::internal::Oauth2JwtSignAlg;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::event::{CreateEvent, ModifyEvent};
@@ -86,11 +88,58 @@ impl JwsKeygen {
                 let v = Value::new_privatebinary(&der);
                 e.add_ava(Attribute::Rs256PrivateKeyDer, v);
             }
+            Self::request_key_object(e)?;
         }
 
         Ok(())
         })
     }
+
+    /// A client that selects a signing algorithm has its signing keys held in its own key
+    /// object, so that they can be rotated and revoked without affecting any other client.
+    fn request_key_object<T: Clone>(e: &mut Entry<EntryInvalid, T>) -> Result<(), OperationError> {
+        let Some(sign_alg) = e.get_ava_single_iutf8(Attribute::OAuth2JwtSignAlg) else {
+            return Ok(());
+        };
+
+        let sign_alg = Oauth2JwtSignAlg::from_str(sign_alg).map_err(|_| {
+            OperationError::InvalidAttribute(format!(
+                "{} must be one of es256 or rs256",
+                Attribute::OAuth2JwtSignAlg
+            ))
+        })?;
+
+        let (marker, other) = match sign_alg {
+            Oauth2JwtSignAlg::Es256 => {
+                (EntryClass::KeyObjectJwtEs256, EntryClass::KeyObjectJwtRs256)
+            }
+            Oauth2JwtSignAlg::Rs256 => {
+                (EntryClass::KeyObjectJwtRs256, EntryClass::KeyObjectJwtEs256)
+            }
+        };
+
+        if !e.attribute_equality(Attribute::Class, &EntryClass::KeyObject.into()) {
+            security_info!(%sign_alg, "creating oauth2 signing key object");
+            e.add_ava(Attribute::Class, EntryClass::KeyObject.to_value());
+
+            // Import the existing es256 key so that tokens issued before the key object
+            // was created can still be verified. This is only done once, so that a key that
+            // is later revoked can't be trusted again.
+            if let Some(v) = e
+                .get_ava_single_private_binary(Attribute::Es256PrivateKeyDer)
+                .map(Value::new_privatebinary)
+            {
+                e.add_ava(Attribute::KeyActionImportJwsEs256, v);
+            }
+        }
+
+        // The keys of the previous algorithm are retained by the key object, so that
+        // tokens that were signed by them remain valid until they expire.
+        e.remove_ava(Attribute::Class, &other.into());
+        e.add_ava(Attribute::Class, marker.to_value());
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
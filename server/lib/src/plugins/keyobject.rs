@@ -116,6 +116,10 @@ impl Plugin for KeyObjectManagement {
                 // Every key object needs at least *one* key it stores.
                 if !key_object_entry
                     .attribute_equality(Attribute::Class, &EntryClass::KeyObjectJwtEs256.into())
+                    && !key_object_entry.attribute_equality(
+                        Attribute::Class,
+                        &EntryClass::KeyObjectSaml2Signing.into(),
+                    )
                 {
                     error!(?object_uuid, "Invalid key object, contains no keys.");
                     return Some(ConsistencyError::KeyProviderNoKeys {
@@ -208,12 +212,39 @@ impl KeyObjectManagement {
                     key_object.jws_es256_assert(Duration::ZERO, &txn_cid)?;
                 }
 
+                if entry.attribute_equality(Attribute::Class, &EntryClass::KeyObjectJwtRs256.into())
+                {
+                    key_object.jws_rs256_assert(Duration::ZERO, &txn_cid)?;
+                }
+
                 if entry
                     .attribute_equality(Attribute::Class, &EntryClass::KeyObjectJweA128GCM.into())
                 {
                     key_object.jwe_a128gcm_assert(Duration::ZERO, &txn_cid)?;
                 }
 
+                if entry.attribute_equality(
+                    Attribute::Class,
+                    &EntryClass::KeyObjectReplTrustAnchor.into(),
+                ) {
+                    key_object.repl_trust_anchor_assert(Duration::ZERO, &txn_cid)?;
+                }
+
+                if entry.attribute_equality(Attribute::Class, &EntryClass::KeyObjectSshCa.into()) {
+                    key_object.ssh_ca_assert(Duration::ZERO, &txn_cid)?;
+                }
+
+                if entry
+                    .attribute_equality(Attribute::Class, &EntryClass::KeyObjectSaml2Signing.into())
+                {
+                    key_object.saml2_signing_assert(Duration::ZERO, &txn_cid)?;
+                }
+
+                if entry.attribute_equality(Attribute::Class, &EntryClass::KeyObjectKerberos.into())
+                {
+                    key_object.kerberos_assert(Duration::ZERO, &txn_cid)?;
+                }
+
                 // Turn that object into it's entry template to create. I think we need to make this
                 // some kind of merge_vs?
                 key_object
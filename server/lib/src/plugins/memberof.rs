@@ -9,10 +9,20 @@
 //
 // As a result, we first need to run refint to clean up all dangling references, then memberof
 // fixes the graph of memberships
+//
+// A group that is nested beneath a parent group is implicitly a member of the parent, so it
+// and its members are memberof every group above it in the hierarchy.
+//
+// Members of a group may be time-bound by member_valid_until. Once their membership lapses
+// they are no longer reflected in memberof. As memberof is only recalculated when entries
+// change, lapsed members are also removed from their groups by a scheduled task.
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
 use std::sync::Arc;
 
+use kanidm_proto::v1::GroupMemberValidUntil;
+
 use crate::entry::{Entry, EntryCommitted, EntrySealed};
 use crate::event::{CreateEvent, DeleteEvent, ModifyEvent};
 use crate::plugins::Plugin;
@@ -21,13 +31,22 @@ use crate::value::PartialValue;
 
 pub struct MemberOf;
 
+/// If the membership of this member in the group has lapsed. Dynamic members are never
+/// time-bound.
+fn membership_lapsed(group: &EntrySealedCommitted, member: Uuid, ct: Duration) -> bool {
+    group.get_members_expired(ct).contains(&member)
+        && !group.attribute_equality(Attribute::DynMember, &PartialValue::Refer(member))
+}
+
 fn do_group_memberof(
     qs: &mut QueryServerWriteTransaction,
     uuid: Uuid,
     tgte: &mut EntryInvalidCommitted,
 ) -> Result<(), OperationError> {
+    let ct = qs.get_curtime();
+
     //  search where we are member
-    let groups = qs
+    let mut groups = qs
         .internal_search(filter!(f_and!([
             f_eq(Attribute::Class, EntryClass::Group.into()),
             f_or!([
@@ -40,6 +59,20 @@ fn do_group_memberof(
             e
         })?;
 
+    // Groups where our membership has lapsed no longer apply.
+    groups.retain(|g| !membership_lapsed(g, uuid, ct));
+
+    // A nested group is a member of its parent.
+    if let Some(parent_uuid) = tgte.get_ava_single_refer(Attribute::ParentGroup) {
+        if !groups.iter().any(|g| g.get_uuid() == parent_uuid) {
+            if let Ok(parent) = qs.internal_search_uuid(parent_uuid) {
+                if parent.attribute_equality(Attribute::Class, &EntryClass::Group.into()) {
+                    groups.push(parent);
+                }
+            }
+        }
+    }
+
     // Ensure we are MO capable. We only add this if it's not already present.
     tgte.add_ava_if_not_exist(Attribute::Class, EntryClass::MemberOf.into());
     // Clear the dmo + mos, we will recreate them now.
@@ -177,6 +210,8 @@ fn do_leaf_memberof(
         tgte.purge_ava(Attribute::DirectMemberOf);
     }
 
+    let ct = qs.get_curtime();
+
     // Now, we go through all the groups, and from each one we update the relevant
     // target entry as needed.
     for group in all_groups {
@@ -189,9 +224,13 @@ fn do_leaf_memberof(
         let member_ref = group.get_ava_refer(Attribute::Member);
         let dynmember_ref = group.get_ava_refer(Attribute::DynMember);
 
+        // Members whose membership has lapsed are skipped.
+        let expired = group.get_members_expired(ct);
+
         let dir_members = member_ref
             .iter()
             .flat_map(|set| set.iter())
+            .filter(|member| !expired.contains(*member))
             .chain(dynmember_ref.iter().flat_map(|set| set.iter()))
             .copied();
 
@@ -293,6 +332,21 @@ fn do_leaf_memberof(
     // Done! 🎉
 }
 
+impl MemberOf {
+    /// Recalculate the memberof of every live entry. This is the repair for
+    /// [ConsistencyError::MemberOfInvalid].
+    #[instrument(level = "debug", name = "memberof::repair", skip_all)]
+    pub(crate) fn repair(qs: &mut QueryServerWriteTransaction) -> Result<(), OperationError> {
+        let all_uuids = qs
+            .internal_search(filter!(f_pres(Attribute::Class)))?
+            .iter()
+            .map(|e| e.get_uuid())
+            .collect();
+
+        apply_memberof(qs, all_uuids)
+    }
+}
+
 // This is how you know the good code is here.
 #[allow(clippy::cognitive_complexity)]
 fn apply_memberof(
@@ -391,6 +445,18 @@ fn apply_memberof(
                     (None, None) => {}
                 };
 
+                // Groups nested beneath this one inherit its memberof too.
+                let children = qs
+                    .internal_search(filter!(f_eq(
+                        Attribute::ParentGroup,
+                        PartialValue::Refer(guuid)
+                    )))
+                    .map_err(|err| {
+                        error!(?err, "internal search failure");
+                        err
+                    })?;
+                affected_uuids.extend(children.iter().map(|child| child.get_uuid()));
+
                 // push the entries to pre/cand
                 changes.push((pre, tgte));
             } else {
@@ -479,6 +545,28 @@ impl Plugin for MemberOf {
         )
     }
 
+    #[instrument(level = "debug", name = "memberof_pre_modify", skip_all)]
+    fn pre_modify(
+        _qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<EntryInvalidCommitted>,
+        _me: &ModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::pre_modify_inner(cand);
+        Ok(())
+    }
+
+    #[instrument(level = "debug", name = "memberof_pre_batch_modify", skip_all)]
+    fn pre_batch_modify(
+        _qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<EntryInvalidCommitted>,
+        _me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::pre_modify_inner(cand);
+        Ok(())
+    }
+
     #[instrument(level = "debug", name = "memberof_post_modify", skip_all)]
     fn post_modify(
         qs: &mut QueryServerWriteTransaction,
@@ -575,6 +663,9 @@ impl Plugin for MemberOf {
         // needing to run queries since we already have every entry on hand
         // from the all_cand search.
         let mut direct_membership_map: BTreeMap<Uuid, BTreeSet<Uuid>> = Default::default();
+        // Time-bound memberships may or may not be reflected depending on whether they had
+        // lapsed when memberof was last calculated, so either is valid.
+        let mut temporary_membership_map: BTreeMap<Uuid, BTreeSet<Uuid>> = Default::default();
 
         let pv_class: PartialValue = EntryClass::Group.into();
 
@@ -585,22 +676,35 @@ impl Plugin for MemberOf {
             }
 
             let group_uuid = entry.get_uuid();
+            let member_valid_until = entry.get_member_valid_until();
 
-            let member_iter = entry
+            for member_uuid in entry
                 .get_ava_refer(Attribute::Member)
                 .into_iter()
                 .flat_map(|set| set.iter())
-                .chain(
-                    entry
-                        .get_ava_refer(Attribute::DynMember)
-                        .into_iter()
-                        .flat_map(|set| set.iter()),
-                );
+            {
+                let membership_map = if member_valid_until.contains_key(member_uuid) {
+                    &mut temporary_membership_map
+                } else {
+                    &mut direct_membership_map
+                };
+                let member_groups = membership_map.entry(*member_uuid).or_default();
+                member_groups.insert(group_uuid);
+            }
 
-            for member_uuid in member_iter {
+            for member_uuid in entry
+                .get_ava_refer(Attribute::DynMember)
+                .into_iter()
+                .flat_map(|set| set.iter())
+            {
                 let member_groups = direct_membership_map.entry(*member_uuid).or_default();
                 member_groups.insert(group_uuid);
             }
+
+            if let Some(parent_uuid) = entry.get_ava_single_refer(Attribute::ParentGroup) {
+                let parent_groups = direct_membership_map.entry(group_uuid).or_default();
+                parent_groups.insert(parent_uuid);
+            }
         }
 
         // for each entry in the DB (live).
@@ -608,6 +712,7 @@ impl Plugin for MemberOf {
             let uuid = e.get_uuid();
 
             let d_groups_set: Option<&BTreeSet<Uuid>> = direct_membership_map.get(&uuid);
+            let t_groups_set: Option<&BTreeSet<Uuid>> = temporary_membership_map.get(&uuid);
 
             trace!(
                 "DMO search groups {:?} -> {:?}",
@@ -619,43 +724,39 @@ impl Plugin for MemberOf {
             // it applies it clones dmo -> mo, so validation of all dmo sets implies mo is
             // valid (and a subset) of dmo.
 
-            match (e.get_ava_set(Attribute::DirectMemberOf), d_groups_set) {
-                (Some(edmos), Some(b)) => {
-                    // Can they both be reference sets?
-                    match edmos.as_refer_set() {
-                        Some(a) => {
-                            let diff: Vec<_> = a.symmetric_difference(b).collect();
-                            if !diff.is_empty() {
-                                error!(
-                                    "MemberOfInvalid: Entry {}, DMO has inconsistencies",
-                                    e.get_display_id(),
-                                );
-                                trace!(entry_direct_member_of = ?a);
-                                trace!(expected_direct_groups = ?b);
-                                trace!(?diff);
-
-                                r.push(Err(ConsistencyError::MemberOfInvalid(e.get_id())));
-                            }
-                        }
-                        _ => {
-                            error!("MemberOfInvalid: Entry {}, DMO has incorrect syntax - should be reference uuid set", e.get_display_id());
-                            r.push(Err(ConsistencyError::MemberOfInvalid(e.get_id())));
-                        }
+            let entry_direct_member_of = match e.get_ava_set(Attribute::DirectMemberOf) {
+                Some(edmos) => match edmos.as_refer_set() {
+                    Some(a) => a.clone(),
+                    None => {
+                        error!("MemberOfInvalid: Entry {}, DMO has incorrect syntax - should be reference uuid set", e.get_display_id());
+                        r.push(Err(ConsistencyError::MemberOfInvalid(e.get_id())));
+                        continue;
                     }
-                }
-                (None, None) => {
-                    // Ok
-                }
-                (entry_direct_member_of, expected_direct_groups) => {
-                    error!(
-                        "MemberOfInvalid directmemberof set and DMO search set differ in presence: {}",
-                        e.get_display_id()
-                    );
-                    // trace!(?e);
-                    trace!(?entry_direct_member_of);
-                    trace!(?expected_direct_groups);
-                    r.push(Err(ConsistencyError::MemberOfInvalid(e.get_id())));
-                }
+                },
+                None => BTreeSet::new(),
+            };
+
+            let expected_direct_groups = d_groups_set.cloned().unwrap_or_default();
+
+            // Any difference must be a time-bound membership that isn't also a permanent one.
+            let diff: Vec<_> = entry_direct_member_of
+                .symmetric_difference(&expected_direct_groups)
+                .filter(|group_uuid| {
+                    expected_direct_groups.contains(*group_uuid)
+                        || !t_groups_set.is_some_and(|t| t.contains(*group_uuid))
+                })
+                .collect();
+
+            if !diff.is_empty() {
+                error!(
+                    "MemberOfInvalid: Entry {}, DMO has inconsistencies",
+                    e.get_display_id(),
+                );
+                trace!(?entry_direct_member_of);
+                trace!(?expected_direct_groups);
+                trace!(?diff);
+
+                r.push(Err(ConsistencyError::MemberOfInvalid(e.get_id())));
             }
         }
 
@@ -664,6 +765,32 @@ impl Plugin for MemberOf {
 }
 
 impl MemberOf {
+    /// Remove the valid until time of members that are no longer in the group, so that
+    /// if they are added again later their membership doesn't lapse unexpectedly.
+    fn pre_modify_inner(cand: &mut [EntryInvalidCommitted]) {
+        for entry in cand.iter_mut() {
+            let stale: BTreeSet<_> = entry
+                .get_ava_set(Attribute::MemberValidUntil)
+                .and_then(|vs| vs.as_utf8_iter())
+                .into_iter()
+                .flatten()
+                .filter(|valid_until| {
+                    GroupMemberValidUntil::from_str(valid_until).is_ok_and(|valid_until| {
+                        !entry.attribute_equality(
+                            Attribute::Member,
+                            &PartialValue::Refer(valid_until.member),
+                        )
+                    })
+                })
+                .map(PartialValue::new_utf8s)
+                .collect();
+
+            if !stale.is_empty() {
+                entry.remove_avas(Attribute::MemberValidUntil, &stale);
+            }
+        }
+    }
+
     fn post_create_inner(
         qs: &mut QueryServerWriteTransaction,
         cand: &[Entry<EntrySealed, EntryCommitted>],
@@ -10,8 +10,10 @@ use crate::entry::{Entry, EntryCommitted, EntryInvalid, EntryNew, EntrySealed};
 use crate::event::{CreateEvent, DeleteEvent, ModifyEvent};
 use crate::prelude::*;
 
+mod announcement;
 mod attrunique;
 mod base;
+mod contractor;
 mod cred_import;
 mod default_values;
 mod domain;
@@ -22,11 +24,14 @@ mod jwskeygen;
 mod keyobject;
 mod memberof;
 mod namehistory;
+mod namespace;
 mod protected;
 mod refint;
 mod session;
 mod spn;
+mod sshkey;
 mod valuedeny;
+mod webhook;
 
 trait Plugin {
     fn id() -> &'static str;
@@ -231,14 +236,20 @@ impl Plugins {
         base::Base::pre_create_transform(qs, cand, ce)?;
         valuedeny::ValueDeny::pre_create_transform(qs, cand, ce)?;
         cred_import::CredImport::pre_create_transform(qs, cand, ce)?;
-        keyobject::KeyObjectManagement::pre_create_transform(qs, cand, ce)?;
+        // Must be before keyobject, as it may request the key object of an oauth2 client.
         jwskeygen::JwsKeygen::pre_create_transform(qs, cand, ce)?;
+        keyobject::KeyObjectManagement::pre_create_transform(qs, cand, ce)?;
         gidnumber::GidNumber::pre_create_transform(qs, cand, ce)?;
         domain::Domain::pre_create_transform(qs, cand, ce)?;
         spn::Spn::pre_create_transform(qs, cand, ce)?;
         default_values::DefaultValues::pre_create_transform(qs, cand, ce)?;
         namehistory::NameHistory::pre_create_transform(qs, cand, ce)?;
         eckeygen::EcdhKeyGen::pre_create_transform(qs, cand, ce)?;
+        contractor::Contractor::pre_create_transform(qs, cand, ce)?;
+        namespace::GroupNamespace::pre_create_transform(qs, cand, ce)?;
+        webhook::Webhook::pre_create_transform(qs, cand, ce)?;
+        announcement::Announcement::pre_create_transform(qs, cand, ce)?;
+        sshkey::SshKeyPolicy::pre_create_transform(qs, cand, ce)?;
         // Should always be last
         attrunique::AttrUnique::pre_create_transform(qs, cand, ce)
     }
@@ -282,6 +293,12 @@ impl Plugins {
         default_values::DefaultValues::pre_modify(qs, pre_cand, cand, me)?;
         namehistory::NameHistory::pre_modify(qs, pre_cand, cand, me)?;
         eckeygen::EcdhKeyGen::pre_modify(qs, pre_cand, cand, me)?;
+        contractor::Contractor::pre_modify(qs, pre_cand, cand, me)?;
+        namespace::GroupNamespace::pre_modify(qs, pre_cand, cand, me)?;
+        webhook::Webhook::pre_modify(qs, pre_cand, cand, me)?;
+        announcement::Announcement::pre_modify(qs, pre_cand, cand, me)?;
+        sshkey::SshKeyPolicy::pre_modify(qs, pre_cand, cand, me)?;
+        memberof::MemberOf::pre_modify(qs, pre_cand, cand, me)?;
         // attr unique should always be last
         attrunique::AttrUnique::pre_modify(qs, pre_cand, cand, me)
     }
@@ -318,6 +335,12 @@ impl Plugins {
         default_values::DefaultValues::pre_batch_modify(qs, pre_cand, cand, me)?;
         namehistory::NameHistory::pre_batch_modify(qs, pre_cand, cand, me)?;
         eckeygen::EcdhKeyGen::pre_batch_modify(qs, pre_cand, cand, me)?;
+        contractor::Contractor::pre_batch_modify(qs, pre_cand, cand, me)?;
+        namespace::GroupNamespace::pre_batch_modify(qs, pre_cand, cand, me)?;
+        webhook::Webhook::pre_batch_modify(qs, pre_cand, cand, me)?;
+        announcement::Announcement::pre_batch_modify(qs, pre_cand, cand, me)?;
+        sshkey::SshKeyPolicy::pre_batch_modify(qs, pre_cand, cand, me)?;
+        memberof::MemberOf::pre_batch_modify(qs, pre_cand, cand, me)?;
         // attr unique should always be last
         attrunique::AttrUnique::pre_batch_modify(qs, pre_cand, cand, me)
     }
@@ -426,5 +449,18 @@ impl Plugins {
         run_verify_plugin!(qs, results, dyngroup::DynGroup);
         run_verify_plugin!(qs, results, memberof::MemberOf);
         run_verify_plugin!(qs, results, spn::Spn);
+        run_verify_plugin!(qs, results, contractor::Contractor);
+    }
+
+    /// Remove references to entries that don't exist, returning how many were referenced.
+    pub fn run_repair_dangling_references(
+        qs: &mut QueryServerWriteTransaction,
+    ) -> Result<usize, OperationError> {
+        refint::ReferentialIntegrity::repair_dangling_references(qs)
+    }
+
+    /// Recalculate the memberof of every live entry.
+    pub fn run_repair_memberof(qs: &mut QueryServerWriteTransaction) -> Result<(), OperationError> {
+        memberof::MemberOf::repair(qs)
     }
 }
@@ -0,0 +1,280 @@
+//! Groups may be nested beneath a parent group to organise them into a hierarchy, such as
+//! `eng_backend` and `eng_sre` beneath `eng`. A nested group is implicitly a member of its
+//! parent, so the group and its members are memberof every group above it. This allows an
+//! access control that targets the members of `eng` to apply to the whole subtree.
+//!
+//! As nesting a group grants its members the memberships of the parent, this plugin asserts
+//! that the parent is a group that doesn't grant high privilege, and that the hierarchy is
+//! never a cycle.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use crate::plugins::Plugin;
+use crate::prelude::*;
+
+pub struct GroupNamespace {}
+
+impl Plugin for GroupNamespace {
+    fn id() -> &'static str {
+        "plugin_group_namespace"
+    }
+
+    #[instrument(
+        level = "debug",
+        name = "group_namespace_pre_create_transform",
+        skip_all
+    )]
+    fn pre_create_transform(
+        qs: &mut QueryServerWriteTransaction,
+        cand: &mut Vec<Entry<EntryInvalid, EntryNew>>,
+        _ce: &CreateEvent,
+    ) -> Result<(), OperationError> {
+        Self::check_parents(qs, cand)
+    }
+
+    #[instrument(level = "debug", name = "group_namespace_pre_modify", skip_all)]
+    fn pre_modify(
+        qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &ModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::check_parents(qs, cand)
+    }
+
+    #[instrument(level = "debug", name = "group_namespace_pre_batch_modify", skip_all)]
+    fn pre_batch_modify(
+        qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::check_parents(qs, cand)
+    }
+}
+
+impl GroupNamespace {
+    fn is_valid_parent<VALID, STATE>(entry: &Entry<VALID, STATE>) -> bool {
+        entry.attribute_equality(Attribute::Class, &EntryClass::Group.into())
+            && !entry.attribute_equality(
+                Attribute::Uuid,
+                &PartialValue::Uuid(UUID_IDM_HIGH_PRIVILEGE),
+            )
+            && !entry.attribute_equality(
+                Attribute::MemberOf,
+                &PartialValue::Refer(UUID_IDM_HIGH_PRIVILEGE),
+            )
+    }
+
+    fn check_parents<STATE>(
+        qs: &mut QueryServerWriteTransaction,
+        cand: &[Entry<EntryInvalid, STATE>],
+    ) -> Result<(), OperationError> {
+        let cand_map: BTreeMap<Uuid, &Entry<EntryInvalid, STATE>> = cand
+            .iter()
+            .filter_map(|entry| entry.get_uuid().map(|uuid| (uuid, entry)))
+            .collect();
+
+        // The parents as they will be once this operation is applied.
+        let parents: BTreeMap<Uuid, Uuid> = cand_map
+            .iter()
+            .filter_map(|(uuid, entry)| {
+                entry
+                    .get_ava_single_refer(Attribute::ParentGroup)
+                    .map(|parent| (*uuid, parent))
+            })
+            .collect();
+
+        for (uuid, parent) in parents.iter() {
+            let valid = match cand_map.get(parent) {
+                Some(entry) => Self::is_valid_parent(*entry),
+                None => qs
+                    .internal_search_uuid(*parent)
+                    .map(|entry| Self::is_valid_parent(entry.as_ref()))
+                    .unwrap_or(false),
+            };
+
+            if !valid {
+                error!(
+                    ?uuid,
+                    ?parent,
+                    "group parent is not a group, or is high privilege"
+                );
+                return Err(OperationError::PL0004GroupParentInvalid);
+            }
+
+            // Walk up to the root of the hierarchy to make sure that this group isn't one of
+            // its own ancestors.
+            let mut seen = BTreeSet::from([*uuid]);
+            let mut ancestor = Some(*parent);
+
+            while let Some(current) = ancestor {
+                if !seen.insert(current) {
+                    error!(?uuid, ?parent, "group parent would create a cycle");
+                    return Err(OperationError::PL0004GroupParentInvalid);
+                }
+
+                ancestor = match parents.get(&current) {
+                    Some(next) => Some(*next),
+                    None if cand_map.contains_key(&current) => None,
+                    None => qs
+                        .internal_search_uuid(current)
+                        .ok()
+                        .and_then(|entry| entry.get_ava_single_refer(Attribute::ParentGroup)),
+                };
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    const ENG_UUID: Uuid = uuid!("0c5d6a3e-7b1f-4e2a-9d8c-3f4a5b6c7d8e");
+    const ENG_BACKEND_UUID: Uuid = uuid!("1d6e7b4f-8c2a-4f3b-8e9d-4a5b6c7d8e9f");
+    const ENG_BACKEND_DB_UUID: Uuid = uuid!("2e7f8c5a-9d3b-4a4c-9f0e-5b6c7d8e9f0a");
+    const PERSON_UUID: Uuid = uuid!("3f8a9d6b-0e4c-4b5d-8a1f-6c7d8e9f0a1b");
+
+    fn group(name: &str, uuid: Uuid, parent: Option<Uuid>) -> EntryInitNew {
+        let mut e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname(name)),
+            (Attribute::Uuid, Value::Uuid(uuid))
+        );
+        if let Some(parent) = parent {
+            e.add_ava(Attribute::ParentGroup, Value::Refer(parent));
+        }
+        e
+    }
+
+    fn assert_memberof(server_txn: &mut QueryServerWriteTransaction, uuid: Uuid, group: Uuid) {
+        let entry = server_txn
+            .internal_search_uuid(uuid)
+            .expect("Unable to find entry");
+        assert!(entry.attribute_equality(Attribute::MemberOf, &PartialValue::Refer(group)));
+    }
+
+    fn assert_not_memberof(server_txn: &mut QueryServerWriteTransaction, uuid: Uuid, group: Uuid) {
+        let entry = server_txn
+            .internal_search_uuid(uuid)
+            .expect("Unable to find entry");
+        assert!(!entry.attribute_equality(Attribute::MemberOf, &PartialValue::Refer(group)));
+    }
+
+    #[qs_test]
+    async fn test_group_namespace_memberof(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let person = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(PERSON_UUID)),
+            (Attribute::DisplayName, Value::new_utf8s("testperson"))
+        );
+
+        let mut eng_backend_db = group("eng_backend_db", ENG_BACKEND_DB_UUID, None);
+        eng_backend_db.add_ava(Attribute::Member, Value::Refer(PERSON_UUID));
+
+        // The whole hierarchy may be created at once.
+        assert!(server_txn
+            .internal_create(vec![
+                person,
+                group("eng", ENG_UUID, None),
+                group("eng_backend", ENG_BACKEND_UUID, Some(ENG_UUID)),
+                eng_backend_db,
+            ])
+            .is_ok());
+
+        assert_not_memberof(&mut server_txn, PERSON_UUID, ENG_BACKEND_UUID);
+
+        // Nesting a group makes it and its members members of every ancestor.
+        assert!(server_txn
+            .internal_modify_uuid(
+                ENG_BACKEND_DB_UUID,
+                &ModifyList::new_purge_and_set(
+                    Attribute::ParentGroup,
+                    Value::Refer(ENG_BACKEND_UUID)
+                )
+            )
+            .is_ok());
+
+        assert_memberof(&mut server_txn, ENG_BACKEND_DB_UUID, ENG_BACKEND_UUID);
+        assert_memberof(&mut server_txn, ENG_BACKEND_DB_UUID, ENG_UUID);
+        assert_memberof(&mut server_txn, PERSON_UUID, ENG_BACKEND_UUID);
+        assert_memberof(&mut server_txn, PERSON_UUID, ENG_UUID);
+
+        // Moving a subtree updates the memberships beneath it.
+        assert!(server_txn
+            .internal_modify_uuid(
+                ENG_BACKEND_UUID,
+                &ModifyList::new_purge(Attribute::ParentGroup)
+            )
+            .is_ok());
+
+        assert_memberof(&mut server_txn, PERSON_UUID, ENG_BACKEND_UUID);
+        assert_not_memberof(&mut server_txn, PERSON_UUID, ENG_UUID);
+
+        // Deleting a parent removes it from the hierarchy.
+        assert!(server_txn.internal_delete_uuid(ENG_BACKEND_UUID).is_ok());
+
+        assert_not_memberof(&mut server_txn, PERSON_UUID, ENG_BACKEND_UUID);
+        let eng_backend_db = server_txn
+            .internal_search_uuid(ENG_BACKEND_DB_UUID)
+            .expect("Unable to find entry");
+        assert!(!eng_backend_db.attribute_pres(Attribute::ParentGroup));
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_group_namespace_invalid_parent(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        assert!(server_txn
+            .internal_create(vec![
+                group("eng", ENG_UUID, None),
+                group("eng_backend", ENG_BACKEND_UUID, Some(ENG_UUID)),
+            ])
+            .is_ok());
+
+        // A group can't be its own ancestor.
+        assert_eq!(
+            server_txn.internal_modify_uuid(
+                ENG_UUID,
+                &ModifyList::new_purge_and_set(
+                    Attribute::ParentGroup,
+                    Value::Refer(ENG_BACKEND_UUID)
+                )
+            ),
+            Err(OperationError::PL0004GroupParentInvalid)
+        );
+
+        assert_eq!(
+            server_txn.internal_modify_uuid(
+                ENG_UUID,
+                &ModifyList::new_purge_and_set(Attribute::ParentGroup, Value::Refer(ENG_UUID))
+            ),
+            Err(OperationError::PL0004GroupParentInvalid)
+        );
+
+        // Nesting beneath a high privilege group would grant that privilege to the members.
+        for parent in [UUID_IDM_ADMINS, UUID_IDM_HIGH_PRIVILEGE] {
+            assert_eq!(
+                server_txn.internal_modify_uuid(
+                    ENG_UUID,
+                    &ModifyList::new_purge_and_set(Attribute::ParentGroup, Value::Refer(parent))
+                ),
+                Err(OperationError::PL0004GroupParentInvalid)
+            );
+        }
+
+        assert!(server_txn.commit().is_ok());
+    }
+}
@@ -27,6 +27,9 @@ lazy_static! {
         Attribute::DomainLdapBasedn,
         Attribute::LdapMaxQueryableAttrs,
         Attribute::LdapAllowUnixPwBind,
+        Attribute::LdapGroupCompat,
+        Attribute::WhitePagesTrustedNetwork,
+        Attribute::WhitePagesRateLimit,
         Attribute::FernetPrivateKeyStr,
         Attribute::Es256PrivateKeyDer,
         Attribute::KeyActionRevoke,
@@ -34,6 +37,7 @@ lazy_static! {
         Attribute::IdVerificationEcKey,
         Attribute::BadlistPassword,
         Attribute::DeniedName,
+        Attribute::DeniedPasswordTerm,
         Attribute::DomainDisplayName,
         Attribute::Image,
         // modification of account policy values for dyngroup.
@@ -45,6 +49,25 @@ lazy_static! {
         Attribute::LimitSearchMaxResults,
         Attribute::LimitSearchMaxFilterTest,
         Attribute::AllowPrimaryCredFallback,
+        Attribute::AuthTrustedNetwork,
+        Attribute::CredentialTypeMinimumUntrustedNetwork,
+        Attribute::AuthLockoutThreshold,
+        Attribute::AuthLockoutWindow,
+        Attribute::AuthLockoutDuration,
+        Attribute::AuthPasswordHistoryCount,
+        Attribute::AllowUnixPassword,
+        Attribute::AllowApiTokens,
+        Attribute::AllowEmailLink,
+        Attribute::PasswordBreachCheck,
+        Attribute::AuthPasswordMaximumAge,
+        Attribute::AuthSessionIdleTimeout,
+        Attribute::LoginHostTag,
+        Attribute::SshKeyAllowedType,
+        Attribute::SshKeyRsaMinimumBits,
+        Attribute::PosixDefaultShell,
+        Attribute::PosixHomeTemplate,
+        Attribute::PosixGecosFormat,
+        Attribute::PosixHomeSkeleton,
         ];
 
         let mut m = HashSet::with_capacity(attrs.len());
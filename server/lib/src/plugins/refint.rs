@@ -121,6 +121,41 @@ impl ReferentialIntegrity {
 
         qs.internal_apply_writable(work_set)
     }
+
+    /// Remove references to entries that no longer exist. This is the repair for
+    /// [ConsistencyError::RefintNotUpheld], and returns the number of missing entries that
+    /// were referenced.
+    #[instrument(level = "debug", name = "refint::repair", skip_all)]
+    pub(crate) fn repair_dangling_references(
+        qs: &mut QueryServerWriteTransaction,
+    ) -> Result<usize, OperationError> {
+        let all_cand = qs.internal_search(filter_all!(f_pres(Attribute::Class)))?;
+
+        let acu_map: HashSet<Uuid> = all_cand.iter().map(|e| e.get_uuid()).collect();
+
+        let ref_types = qs.get_schema().get_reference_types();
+
+        let dangling: BTreeSet<Uuid> = all_cand
+            .iter()
+            .flat_map(|c| {
+                ref_types
+                    .values()
+                    .filter_map(|rtype| c.get_ava_set(&rtype.name))
+                    .filter_map(|vs| vs.as_ref_uuid_iter())
+                    .flatten()
+                    .collect::<Vec<_>>()
+            })
+            .filter(|u| !acu_map.contains(u))
+            .collect();
+
+        if dangling.is_empty() {
+            return Ok(0);
+        }
+
+        let count = dangling.len();
+        Self::remove_references(qs, dangling.into_iter().collect())?;
+        Ok(count)
+    }
 }
 
 impl Plugin for ReferentialIntegrity {
@@ -1081,6 +1116,7 @@ mod tests {
                         state: SessionState::NeverExpires,
                         issued_at,
                         rs_uuid,
+                        revoke_reason: None,
                     },
                 )
             ),
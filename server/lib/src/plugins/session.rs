@@ -10,7 +10,7 @@
 use crate::event::ModifyEvent;
 use crate::plugins::Plugin;
 use crate::prelude::*;
-use crate::value::SessionState;
+use crate::value::{AuthType, SessionState};
 use std::collections::BTreeSet;
 use std::sync::Arc;
 use time::OffsetDateTime;
@@ -83,7 +83,10 @@ impl SessionConsistency {
                             }
                             SessionState::ExpiresAt(_) |
                             SessionState::NeverExpires =>
-                                if !cred_ids.contains(&session.cred_id) {
+                                // Impersonation sessions were not issued by a credential of
+                                // the account, and are bounded by their own short expiry.
+                                if session.type_ != AuthType::Impersonation
+                                    && !cred_ids.contains(&session.cred_id) {
                                     info!(%session_id, "Revoking auth session whose issuing credential no longer exists");
                                     Some(PartialValue::Refer(*session_id))
                                 } else {
@@ -399,6 +402,7 @@ mod tests {
                         state,
                         issued_at,
                         rs_uuid,
+                        revoke_reason: None,
                     },
                 )
             ),
@@ -572,6 +576,7 @@ mod tests {
                         state: SessionState::NeverExpires,
                         issued_at,
                         rs_uuid,
+                        revoke_reason: None,
                     },
                 )
             ),
@@ -733,6 +738,7 @@ mod tests {
                 state: SessionState::NeverExpires,
                 issued_at,
                 rs_uuid,
+                revoke_reason: None,
             },
         );
 
@@ -0,0 +1,283 @@
+//! Ssh public keys may be given an expiry, after which they are no longer distributed to
+//! clients. Each expiry must refer to a key of the account, and is removed along with the key.
+//! Keys that are added to an existing account must be permitted by its account policy. The
+//! policy is resolved from group memberships, so it can't be applied to keys of new entries.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use kanidm_proto::v1::SshPublicKeyExpiry;
+use sshkey_attest::proto::PublicKey as SshPublicKey;
+
+use crate::idm::group::load_account_policy;
+use crate::plugins::Plugin;
+use crate::prelude::*;
+
+pub struct SshKeyPolicy {}
+
+impl Plugin for SshKeyPolicy {
+    fn id() -> &'static str {
+        "plugin_sshkey_policy"
+    }
+
+    #[instrument(level = "debug", name = "sshkey_pre_create_transform", skip_all)]
+    fn pre_create_transform(
+        _qs: &mut QueryServerWriteTransaction,
+        cand: &mut Vec<Entry<EntryInvalid, EntryNew>>,
+        _ce: &CreateEvent,
+    ) -> Result<(), OperationError> {
+        cand.iter_mut()
+            .try_for_each(|e| Self::expiry_inner(e, None))
+    }
+
+    #[instrument(level = "debug", name = "sshkey_pre_modify", skip_all)]
+    fn pre_modify(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &ModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::modify_inner(qs, pre_cand, cand)
+    }
+
+    #[instrument(level = "debug", name = "sshkey_pre_batch_modify", skip_all)]
+    fn pre_batch_modify(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::modify_inner(qs, pre_cand, cand)
+    }
+}
+
+impl SshKeyPolicy {
+    fn modify_inner(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut [Entry<EntryInvalid, EntryCommitted>],
+    ) -> Result<(), OperationError> {
+        for (pre, post) in pre_cand.iter().zip(cand.iter_mut()) {
+            let pre_keys = pre
+                .get_ava_set(Attribute::SshPublicKey)
+                .and_then(|vs| vs.as_sshkey_map());
+
+            Self::expiry_inner(post, pre_keys)?;
+
+            let added: Vec<&SshPublicKey> = post
+                .get_ava_set(Attribute::SshPublicKey)
+                .and_then(|vs| vs.as_sshkey_map())
+                .into_iter()
+                .flatten()
+                .filter(|(tag, pk)| pre_keys.and_then(|keys| keys.get(*tag)) != Some(*pk))
+                .map(|(_, pk)| pk)
+                .collect();
+
+            if added.is_empty() {
+                continue;
+            }
+
+            let account_policy = load_account_policy(pre, qs)?;
+
+            if let Some(pk) = added
+                .into_iter()
+                .find(|pk| !account_policy.ssh_key_permitted(pk))
+            {
+                error!(
+                    entry_id = %post.get_display_id(),
+                    key_type = %pk.key_type.name,
+                    "ssh public key is not permitted by the account policy"
+                );
+                return Err(OperationError::PL0003SshPublicKeyPolicyDenied);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that each expiry refers to a key of the entry. The expiries of keys that were
+    /// removed from the entry are removed too.
+    fn expiry_inner<T: Clone>(
+        e: &mut Entry<EntryInvalid, T>,
+        pre_keys: Option<&BTreeMap<String, SshPublicKey>>,
+    ) -> Result<(), OperationError> {
+        let keys = e
+            .get_ava_set(Attribute::SshPublicKey)
+            .and_then(|vs| vs.as_sshkey_map());
+
+        let mut labels = BTreeSet::new();
+        let mut removed = Vec::new();
+
+        for expiry in e
+            .get_ava_set(Attribute::SshPublicKeyExpiry)
+            .and_then(|vs| vs.as_utf8_iter())
+            .into_iter()
+            .flatten()
+        {
+            let invalid = || {
+                OperationError::InvalidAttribute(format!("Invalid ssh public key expiry {expiry}"))
+            };
+
+            let SshPublicKeyExpiry { label, .. } =
+                SshPublicKeyExpiry::from_str(expiry).map_err(|_| invalid())?;
+
+            if keys.is_some_and(|keys| keys.contains_key(&label)) {
+                // Only one expiry may be set for each key.
+                if !labels.insert(label) {
+                    return Err(invalid());
+                }
+            } else if pre_keys.is_some_and(|keys| keys.contains_key(&label)) {
+                removed.push(PartialValue::new_utf8s(expiry));
+            } else {
+                error!(
+                    ?label,
+                    "ssh public key expiry refers to a key that does not exist"
+                );
+                return Err(invalid());
+            }
+        }
+
+        for pv in removed {
+            e.remove_ava(Attribute::SshPublicKeyExpiry, &pv);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    const SSH_ED25519: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAeGW1P6Pc2rPq0XqbRaDKBcXZUPRklo0L1EyR30CwoP";
+
+    const SSH_RSA_2048: &str = concat!(
+        "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQDTcXpclurQpyOHZBM/cDY9EvInSYkYSGe51by/wJP0Njgi",
+        "GZUJ3HTaPqoGWux0PKd7KJki+onLYt4IwDV1RhV/GtMML2U9v94+pA8RIK4khCxvpUxlM7Kt/svjOzzzqiZfKdV37/",
+        "OUXmM7bwVGOvm3EerDOwmO/QdzNGfkca12aWLoz97YrleXnCoAzr3IN7j3rwmfJGDyuUtGTdmyS/QWhK9FPr8Ic3eM",
+        "QK1JSAQqVfGhA8lLbJHmnQ/b/KMl2lzzp7SXej0wPUfvI/IP3NGb8irLzq8+JssAzXGJ+HMql+mNHiSuPaktbFzZ6y",
+        "ikMR6Rx/psU07nAkxKZDEYpNVv"
+    );
+
+    fn sshkey(tag: &str, key: &str) -> Value {
+        Value::new_sshkey_str(tag, key).expect("Invalid ssh key")
+    }
+
+    #[qs_test]
+    async fn test_sshkey_expiry(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let uuid = Uuid::new_v4();
+        let person = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson")),
+            (Attribute::Uuid, Value::Uuid(uuid)),
+            (Attribute::SshPublicKey, sshkey("laptop", SSH_ED25519))
+        );
+
+        assert!(server_txn.internal_create(vec![person]).is_ok());
+
+        let set_expiry = |expiry: &str| {
+            ModifyList::new_append(Attribute::SshPublicKeyExpiry, Value::new_utf8s(expiry))
+        };
+
+        // The expiry must be valid, and refer to a key that exists.
+        assert!(server_txn
+            .internal_modify_uuid(uuid, &set_expiry("laptop"))
+            .is_err());
+        assert!(server_txn
+            .internal_modify_uuid(uuid, &set_expiry("desktop=2030-01-01T00:00:00Z"))
+            .is_err());
+        assert!(server_txn
+            .internal_modify_uuid(uuid, &set_expiry("laptop=2030-01-01T00:00:00Z"))
+            .is_ok());
+        // Only one expiry for each key.
+        assert!(server_txn
+            .internal_modify_uuid(uuid, &set_expiry("laptop=2031-01-01T00:00:00Z"))
+            .is_err());
+
+        // Removing the key removes the expiry.
+        assert!(server_txn
+            .internal_modify_uuid(uuid, &ModifyList::new_purge(Attribute::SshPublicKey))
+            .is_ok());
+
+        let e = server_txn.internal_search_uuid(uuid).unwrap();
+        assert!(!e.attribute_pres(Attribute::SshPublicKeyExpiry));
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_sshkey_account_policy(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let uuid = Uuid::new_v4();
+        let person = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson")),
+            (Attribute::Uuid, Value::Uuid(uuid))
+        );
+
+        assert!(server_txn.internal_create(vec![person]).is_ok());
+
+        assert!(server_txn
+            .internal_modify_uuid(
+                UUID_IDM_ALL_ACCOUNTS,
+                &ModifyList::new_purge_and_set(
+                    Attribute::SshKeyRsaMinimumBits,
+                    Value::Uint32(3072)
+                )
+            )
+            .is_ok());
+
+        assert_eq!(
+            server_txn.internal_modify_uuid(
+                uuid,
+                &ModifyList::new_append(Attribute::SshPublicKey, sshkey("rsa", SSH_RSA_2048))
+            ),
+            Err(OperationError::PL0003SshPublicKeyPolicyDenied)
+        );
+        assert!(server_txn
+            .internal_modify_uuid(
+                uuid,
+                &ModifyList::new_append(Attribute::SshPublicKey, sshkey("ed25519", SSH_ED25519))
+            )
+            .is_ok());
+
+        // Keys that were already present are not checked when other keys change.
+        let modlist = ModifyList::new_purge_and_set(
+            Attribute::SshKeyAllowedType,
+            Value::new_iutf8("ssh-rsa"),
+        );
+        assert!(server_txn
+            .internal_modify_uuid(UUID_IDM_ALL_ACCOUNTS, &modlist)
+            .is_ok());
+
+        assert!(server_txn
+            .internal_modify_uuid(
+                uuid,
+                &ModifyList::new_purge_and_set(Attribute::DisplayName, Value::new_utf8s("renamed"))
+            )
+            .is_ok());
+        assert_eq!(
+            server_txn.internal_modify_uuid(
+                uuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::SshPublicKey,
+                    sshkey("other", SSH_ED25519)
+                )
+            ),
+            Err(OperationError::PL0003SshPublicKeyPolicyDenied)
+        );
+
+        assert!(server_txn.commit().is_ok());
+    }
+}
@@ -0,0 +1,171 @@
+//! Webhooks are sent events signed with a secret that is shared with the receiver. The
+//! secret is generated when the webhook is created, and regenerated if it is removed so
+//! that it can be rotated. The filters of the webhook are also checked to ensure they
+//! refer to classes and attributes that exist.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::idm::webhook::WebhookFilter;
+use crate::plugins::Plugin;
+use crate::prelude::*;
+use crate::schema::SchemaTransaction;
+use crate::utils::password_from_random;
+
+pub struct Webhook {}
+
+impl Plugin for Webhook {
+    fn id() -> &'static str {
+        "plugin_webhook"
+    }
+
+    #[instrument(level = "debug", name = "webhook_pre_create_transform", skip_all)]
+    fn pre_create_transform(
+        qs: &mut QueryServerWriteTransaction,
+        cand: &mut Vec<Entry<EntryInvalid, EntryNew>>,
+        _ce: &CreateEvent,
+    ) -> Result<(), OperationError> {
+        Self::modify_inner(qs, cand)
+    }
+
+    #[instrument(level = "debug", name = "webhook_pre_modify", skip_all)]
+    fn pre_modify(
+        qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &ModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::modify_inner(qs, cand)
+    }
+
+    #[instrument(level = "debug", name = "webhook_pre_batch_modify", skip_all)]
+    fn pre_batch_modify(
+        qs: &mut QueryServerWriteTransaction,
+        _pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
+        _me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::modify_inner(qs, cand)
+    }
+}
+
+impl Webhook {
+    fn modify_inner<T: Clone>(
+        qs: &mut QueryServerWriteTransaction,
+        cand: &mut [Entry<EntryInvalid, T>],
+    ) -> Result<(), OperationError> {
+        let schema = qs.get_schema();
+
+        cand.iter_mut()
+            .filter(|e| e.attribute_equality(Attribute::Class, &EntryClass::Webhook.into()))
+            .try_for_each(|e| {
+                if !e.attribute_pres(Attribute::WebhookSecret) {
+                    security_info!("regenerating webhook secret");
+                    let v = Value::SecretValue(password_from_random());
+                    e.add_ava(Attribute::WebhookSecret, v);
+                }
+
+                for filter in e
+                    .get_ava_iter_iutf8(Attribute::WebhookFilter)
+                    .into_iter()
+                    .flatten()
+                {
+                    let webhook_filter = WebhookFilter::from_str(filter)?;
+
+                    let class_valid = webhook_filter
+                        .class
+                        .as_deref()
+                        .map_or(true, |class| schema.get_classes().contains_key(class));
+                    let attr_valid = webhook_filter
+                        .attr
+                        .as_ref()
+                        .map_or(true, |attr| schema.get_attributes().contains_key(attr));
+
+                    if !class_valid || !attr_valid {
+                        error!(
+                            ?filter,
+                            "webhook filter refers to a class or attribute that does not exist"
+                        );
+                        return Err(OperationError::InvalidAttribute(format!(
+                            "Invalid webhook filter {filter}"
+                        )));
+                    }
+                }
+
+                Ok(())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    fn webhook(name: &str, uuid: Uuid, filter: &str) -> EntryInitNew {
+        entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Webhook.to_value()),
+            (Attribute::Name, Value::new_iname(name)),
+            (Attribute::Uuid, Value::Uuid(uuid)),
+            (
+                Attribute::WebhookUrl,
+                Value::Url(Url::parse("https://hooks.example.com/kanidm").unwrap())
+            ),
+            (Attribute::WebhookFilter, Value::new_iutf8(filter))
+        )
+    }
+
+    #[qs_test]
+    async fn test_webhook_secret_and_filters(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        // Filters must refer to classes and attributes that exist.
+        assert!(server_txn
+            .internal_create(vec![webhook(
+                "webhook_a",
+                Uuid::new_v4(),
+                "created:nothing"
+            )])
+            .is_err());
+        assert!(server_txn
+            .internal_create(vec![webhook(
+                "webhook_b",
+                Uuid::new_v4(),
+                "modified:group:nothing"
+            )])
+            .is_err());
+
+        let uuid = Uuid::new_v4();
+        assert!(server_txn
+            .internal_create(vec![webhook("webhook_c", uuid, "modified:group:member")])
+            .is_ok());
+
+        let secret = server_txn
+            .internal_search_uuid(uuid)
+            .ok()
+            .and_then(|e| {
+                e.get_ava_single_secret(Attribute::WebhookSecret)
+                    .map(str::to_string)
+            })
+            .expect("Webhook secret was not generated");
+
+        // Purging the secret rotates it.
+        assert!(server_txn
+            .internal_modify_uuid(uuid, &ModifyList::new_purge(Attribute::WebhookSecret))
+            .is_ok());
+
+        let e = server_txn.internal_search_uuid(uuid).unwrap();
+        assert!(e
+            .get_ava_single_secret(Attribute::WebhookSecret)
+            .is_some_and(|rotated| rotated != secret));
+
+        assert!(server_txn
+            .internal_modify_uuid(
+                uuid,
+                &ModifyList::new_append(Attribute::WebhookFilter, Value::new_iutf8("renamed:*"))
+            )
+            .is_err());
+
+        assert!(server_txn.commit().is_ok());
+    }
+}
@@ -271,6 +271,15 @@ impl QueryServerWriteTransaction<'_> {
             self.changed_flags.insert(ChangeFlag::APPLICATION)
         }
 
+        if !self.changed_flags.contains(ChangeFlag::ANNOUNCEMENT)
+            && cand
+                .iter()
+                .chain(pre_cand.iter().map(|e| e.as_ref()))
+                .any(|e| e.attribute_equality(Attribute::Class, &EntryClass::Announcement.into()))
+        {
+            self.changed_flags.insert(ChangeFlag::ANNOUNCEMENT)
+        }
+
         if !self.changed_flags.contains(ChangeFlag::SYNC_AGREEMENT)
             && cand
                 .iter()
@@ -302,6 +311,25 @@ impl QueryServerWriteTransaction<'_> {
     pub fn consumer_apply_changes(
         &mut self,
         ctx: ReplIncrementalContext,
+    ) -> Result<ConsumerState, OperationError> {
+        self.consumer_apply_changes_inner(ctx, None)
+    }
+
+    /// Apply changes from a supplier, refusing them if they are further ahead of our clock
+    /// than `clock_skew_tolerance`. Changes within the tolerance advance our change clock so
+    /// that changes made on this server after this point are still ordered after them.
+    pub fn consumer_apply_changes_with_tolerance(
+        &mut self,
+        ctx: ReplIncrementalContext,
+        clock_skew_tolerance: Duration,
+    ) -> Result<ConsumerState, OperationError> {
+        self.consumer_apply_changes_inner(ctx, Some(clock_skew_tolerance))
+    }
+
+    fn consumer_apply_changes_inner(
+        &mut self,
+        ctx: ReplIncrementalContext,
+        clock_skew_tolerance: Option<Duration>,
     ) -> Result<ConsumerState, OperationError> {
         match ctx {
             ReplIncrementalContext::DomainMismatch => {
@@ -311,6 +339,8 @@ impl QueryServerWriteTransaction<'_> {
             }
             ReplIncrementalContext::NoChangesAvailable => {
                 info!("no changes are available");
+                // We are up to date with the supplier.
+                self.get_metrics().record_replication(self.get_curtime(), Duration::ZERO);
                 Ok(ConsumerState::Ok)
             }
             ReplIncrementalContext::RefreshRequired => {
@@ -339,6 +369,7 @@ impl QueryServerWriteTransaction<'_> {
                 schema_entries,
                 meta_entries,
                 entries,
+                clock_skew_tolerance,
             ),
         }
     }
@@ -353,6 +384,7 @@ impl QueryServerWriteTransaction<'_> {
         ctx_schema_entries: Vec<ReplIncrementalEntryV1>,
         ctx_meta_entries: Vec<ReplIncrementalEntryV1>,
         ctx_entries: Vec<ReplIncrementalEntryV1>,
+        clock_skew_tolerance: Option<Duration>,
     ) -> Result<ConsumerState, OperationError> {
         if ctx_domain_version < DOMAIN_MIN_LEVEL {
             error!("Unable to proceed with consumer incremental - incoming domain level is lower than our minimum supported level. {} < {}", ctx_domain_version, DOMAIN_MIN_LEVEL);
@@ -381,6 +413,23 @@ impl QueryServerWriteTransaction<'_> {
             return Err(OperationError::ReplDomainUuidMismatch);
         }
 
+        // The latest change from any server in this context.
+        let ctx_ts_max = ctx_ranges.values().map(|range| range.ts_max).max();
+
+        // Changes from a server with a clock that is ahead of ours will win every conflict
+        // until our clock catches up, so refuse them if they are too far ahead.
+        if let (Some(tolerance), Some(ctx_ts_max)) = (clock_skew_tolerance, ctx_ts_max) {
+            let curtime = self.get_curtime();
+            if ctx_ts_max > curtime + tolerance {
+                error!(
+                    skew_secs = (ctx_ts_max - curtime).as_secs(),
+                    tolerance_secs = tolerance.as_secs(),
+                    "Unable to proceed with consumer incremental - incoming changes are ahead of our clock by more than the allowed tolerance. You must check the clocks of this server and its replication partners."
+                );
+                return Err(OperationError::ReplClockSkewExceedsTolerance);
+            }
+        }
+
         // Preflight checks of the incoming RUV to ensure it's in a good state.
         let txn_cid = self.get_cid().clone();
         let ruv = self.be_txn.get_ruv_write();
@@ -465,6 +514,16 @@ impl QueryServerWriteTransaction<'_> {
             error!(?err, "Unable to update RUV with supplier ranges.");
         })?;
 
+        if let (Some(_), Some(ctx_ts_max)) = (clock_skew_tolerance, ctx_ts_max) {
+            self.advance_cid(ctx_ts_max);
+        }
+
+        let curtime = self.get_curtime();
+        let lag = ctx_ts_max
+            .and_then(|ts_max| curtime.checked_sub(ts_max))
+            .unwrap_or_default();
+        self.get_metrics().record_replication(curtime, lag);
+
         Ok(ConsumerState::Ok)
     }
 
@@ -647,6 +706,7 @@ impl QueryServerWriteTransaction<'_> {
                 | ChangeFlag::OAUTH2
                 | ChangeFlag::DOMAIN
                 | ChangeFlag::APPLICATION
+                | ChangeFlag::ANNOUNCEMENT
                 | ChangeFlag::SYSTEM_CONFIG
                 | ChangeFlag::SYNC_AGREEMENT
                 | ChangeFlag::KEY_MATERIAL,
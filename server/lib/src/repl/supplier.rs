@@ -6,27 +6,49 @@ use crate::be::BackendTransaction;
 use crate::prelude::*;
 
 use crate::be::keystorage::{KeyHandle, KeyHandleId};
-use kanidm_lib_crypto::mtls::build_self_signed_server_and_client_identity;
+use kanidm_lib_crypto::mtls::{expires_within_days, is_issued_by};
 use kanidm_lib_crypto::prelude::{PKey, Private, X509};
 
 impl QueryServerWriteTransaction<'_> {
+    /// Ensure that the domain key object is able to issue replication identities. Domains
+    /// created before replication trust anchors existed are upgraded here.
+    fn supplier_assert_repl_trust_anchor(&mut self) -> Result<(), OperationError> {
+        let domain_entry = self.internal_search_uuid(UUID_DOMAIN_INFO)?;
+
+        if domain_entry.attribute_equality(
+            Attribute::Class,
+            &EntryClass::KeyObjectReplTrustAnchor.into(),
+        ) {
+            return Ok(());
+        }
+
+        debug!("Adding replication trust anchor to domain key object");
+
+        self.internal_modify_uuid(
+            UUID_DOMAIN_INFO,
+            &ModifyList::new_append(
+                Attribute::Class,
+                EntryClass::KeyObjectReplTrustAnchor.into(),
+            ),
+        )?;
+
+        // Load the new anchor into the key providers.
+        self.reload()
+    }
+
     fn supplier_generate_key_cert(
         &mut self,
         domain_name: &str,
     ) -> Result<(PKey<Private>, X509), OperationError> {
         // Invalid, must need to re-generate.
+        self.supplier_assert_repl_trust_anchor()?;
+
         let s_uuid = self.get_server_uuid();
+        let ct = self.get_curtime();
 
-        let (private, x509) = build_self_signed_server_and_client_identity(
-            s_uuid,
-            domain_name,
-            REPL_MTLS_CERTIFICATE_DAYS,
-        )
-        .map_err(|err| {
-            error!(?err, "Unable to generate self signed key/cert");
-            // What error?
-            OperationError::CryptographyError
-        })?;
+        let (private, x509) =
+            self.get_domain_key_object_handle()?
+                .repl_identity_issue(s_uuid, domain_name, ct)?;
 
         let kh = KeyHandle::X509Key {
             private: private.clone(),
@@ -54,6 +76,7 @@ impl QueryServerWriteTransaction<'_> {
     ) -> Result<(PKey<Private>, X509), OperationError> {
         // Later we need to put this through a HSM or similar, but we will always need a way
         // to persist a handle, so we still need the db write and load components.
+        self.supplier_assert_repl_trust_anchor()?;
 
         // Does the handle exist?
         let maybe_key_handle = self
@@ -64,19 +87,51 @@ impl QueryServerWriteTransaction<'_> {
                 err
             })?;
 
-        // Can you process the keyhandle?
-        let key_cert = match maybe_key_handle {
+        let (private, x509) = match maybe_key_handle {
             Some(KeyHandle::X509Key { private, x509 }) => (private, x509),
-            /*
-            Some(Keyhandle::...) => {
-                // invalid key
-                // error? regenerate?
+            Some(_) => {
+                error!("Replication key handle contains an unexpected key type");
+                return Err(OperationError::InvalidState);
             }
-            */
-            None => self.supplier_generate_key_cert(domain_name)?,
+            None => return self.supplier_generate_key_cert(domain_name),
         };
 
-        Ok(key_cert)
+        let expiring =
+            expires_within_days(&x509, REPL_MTLS_IDENTITY_RENEW_DAYS).map_err(|err| {
+                error!(?err, "Unable to check replication certificate expiry");
+                OperationError::CryptographyError
+            })?;
+
+        if expiring {
+            info!("Replication certificate is near expiry, renewing");
+            return self.supplier_generate_key_cert(domain_name);
+        }
+
+        // Self signed certificates were created before trust anchors existed. Partners may
+        // have pinned them, so they are kept until they expire or are manually renewed.
+        let self_signed = is_issued_by(&x509, &x509);
+
+        if !self_signed {
+            // An identity from an anchor that was revoked, or that belonged to a domain we
+            // were refreshed from, must be reissued from a current anchor.
+            let trust_anchors = self.supplier_get_trust_anchors()?;
+
+            if !trust_anchors
+                .iter()
+                .any(|anchor| is_issued_by(&x509, anchor))
+            {
+                info!("Replication certificate is not trusted by the domain, renewing");
+                return self.supplier_generate_key_cert(domain_name);
+            }
+        }
+
+        Ok((private, x509))
+    }
+
+    /// The certificate authorities that replication partners in this domain are issued
+    /// identities from.
+    pub fn supplier_get_trust_anchors(&mut self) -> Result<Vec<X509>, OperationError> {
+        self.get_domain_key_object_handle()?.repl_trust_anchors()
     }
 }
 
@@ -3763,3 +3763,150 @@ async fn test_repl_increment_consumer_lagging_refresh(
 // Test change of domain version over incremental.
 //
 // todo when I have domain version migrations working.
+
+// Test that changes from a supplier with a clock that is ahead of ours are refused when
+// they exceed the tolerance, and otherwise advance our change clock.
+#[qs_pair_test]
+async fn test_repl_increment_clock_skew_tolerance(server_a: &QueryServer, server_b: &QueryServer) {
+    let ct = duration_from_epoch_now();
+
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let mut server_b_txn = server_b.read().await.unwrap();
+
+    assert!(repl_initialise(&mut server_b_txn, &mut server_a_txn)
+        .and_then(|_| server_a_txn.commit())
+        .is_ok());
+    drop(server_b_txn);
+
+    // The clock of server b is ahead.
+    let skew = Duration::from_secs(30);
+    let mut server_b_txn = server_b.write(ct + skew).await.unwrap();
+    let t_uuid = Uuid::new_v4();
+    assert!(server_b_txn
+        .internal_create(vec![entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(t_uuid)),
+            (Attribute::Description, Value::new_utf8s("testperson1")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+        ),])
+        .is_ok());
+    server_b_txn.commit().expect("Failed to commit");
+
+    let mut server_a_txn = server_a.read().await.unwrap();
+    let a_ruv_range = server_a_txn
+        .consumer_get_state()
+        .expect("Unable to access RUV range");
+    drop(server_a_txn);
+
+    let mut server_b_txn = server_b.read().await.unwrap();
+    let changes = server_b_txn
+        .supplier_provide_changes(a_ruv_range)
+        .expect("Unable to generate supplier changes");
+    drop(server_b_txn);
+
+    // Beyond the tolerance, the changes are refused.
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let result =
+        server_a_txn.consumer_apply_changes_with_tolerance(changes, Duration::from_secs(10));
+    assert!(matches!(
+        result,
+        Err(OperationError::ReplClockSkewExceedsTolerance)
+    ));
+    drop(server_a_txn);
+
+    let mut server_a_txn = server_a.read().await.unwrap();
+    let a_ruv_range = server_a_txn
+        .consumer_get_state()
+        .expect("Unable to access RUV range");
+    drop(server_a_txn);
+
+    let mut server_b_txn = server_b.read().await.unwrap();
+    let changes = server_b_txn
+        .supplier_provide_changes(a_ruv_range)
+        .expect("Unable to generate supplier changes");
+    drop(server_b_txn);
+
+    // Within the tolerance they are accepted.
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    server_a_txn
+        .consumer_apply_changes_with_tolerance(changes, Duration::from_secs(60))
+        .expect("Unable to apply changes to consumer.");
+    assert!(server_a_txn.internal_search_uuid(t_uuid).is_ok());
+    server_a_txn.commit().expect("Failed to commit");
+
+    // Changes made on server a after this point are ordered after the changes from server b,
+    // even though the clock of server a is behind.
+    let server_a_txn = server_a.write(ct + Duration::from_secs(1)).await.unwrap();
+    assert!(server_a_txn.get_cid().ts > ct + skew);
+    drop(server_a_txn);
+}
+
+// Test that replication identities are issued from the trust anchor of the domain, and that
+// a refreshed server reissues its identity from the anchor it received.
+#[qs_pair_test]
+async fn test_repl_identity_trust_anchor(server_a: &QueryServer, server_b: &QueryServer) {
+    use kanidm_lib_crypto::mtls::is_issued_by;
+
+    let ct = duration_from_epoch_now();
+
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let (_, a_cert) = server_a_txn
+        .supplier_get_key_cert("a.example.com")
+        .expect("Unable to get replication identity");
+    let a_anchors = server_a_txn
+        .supplier_get_trust_anchors()
+        .expect("Unable to get trust anchors");
+    server_a_txn.commit().expect("Failed to commit");
+
+    assert!(!a_anchors.is_empty());
+    assert!(a_anchors.iter().any(|anchor| is_issued_by(&a_cert, anchor)));
+
+    // The identity is stable once issued.
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let (_, a_cert_again) = server_a_txn
+        .supplier_get_key_cert("a.example.com")
+        .expect("Unable to get replication identity");
+    server_a_txn.commit().expect("Failed to commit");
+    assert_eq!(a_cert.to_der().ok(), a_cert_again.to_der().ok());
+
+    // Server b starts with its own anchor, so a would not trust it.
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    let (_, b_cert) = server_b_txn
+        .supplier_get_key_cert("b.example.com")
+        .expect("Unable to get replication identity");
+    server_b_txn.commit().expect("Failed to commit");
+    assert!(!a_anchors.iter().any(|anchor| is_issued_by(&b_cert, anchor)));
+
+    // Refresh b from a.
+    let mut server_a_txn = server_a.read().await.unwrap();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    assert!(repl_initialise(&mut server_a_txn, &mut server_b_txn)
+        .and_then(|_| server_b_txn.commit())
+        .is_ok());
+    drop(server_a_txn);
+
+    // Now b shares the anchors of a, and reissues its identity from them.
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    let b_anchors = server_b_txn
+        .supplier_get_trust_anchors()
+        .expect("Unable to get trust anchors");
+    let (_, b_cert) = server_b_txn
+        .supplier_get_key_cert("b.example.com")
+        .expect("Unable to get replication identity");
+    server_b_txn.commit().expect("Failed to commit");
+
+    assert_eq!(
+        a_anchors
+            .iter()
+            .map(|a| a.to_der().ok())
+            .collect::<Vec<_>>(),
+        b_anchors
+            .iter()
+            .map(|a| a.to_der().ok())
+            .collect::<Vec<_>>()
+    );
+    assert!(a_anchors.iter().any(|anchor| is_issued_by(&b_cert, anchor)));
+}
@@ -257,6 +257,9 @@ impl SchemaAttribute {
             SyntaxType::ApplicationPassword => {
                 matches!(v, PartialValue::Uuid(_)) || matches!(v, PartialValue::Refer(_))
             }
+            // Usage is removed by the uuid of the credential.
+            SyntaxType::CredentialUsage => matches!(v, PartialValue::Uuid(_)),
+            SyntaxType::PublicBinary => matches!(v, PartialValue::PublicBinary(_)),
         };
         if r {
             Ok(())
@@ -321,6 +324,8 @@ impl SchemaAttribute {
                 SyntaxType::HexString => matches!(v, Value::HexString(_)),
                 SyntaxType::Certificate => matches!(v, Value::Certificate(_)),
                 SyntaxType::ApplicationPassword => matches!(v, Value::ApplicationPassword(..)),
+                SyntaxType::CredentialUsage => matches!(v, Value::CredentialUsage(..)),
+                SyntaxType::PublicBinary => matches!(v, Value::PublicBinary(..)),
             };
         if r {
             Ok(())
@@ -1280,6 +1285,21 @@ impl SchemaWriteTransaction<'_> {
                     syntax: SyntaxType::Boolean,
                 },
             );
+        self.attributes.insert(
+            Attribute::AcpStaged,
+            SchemaAttribute {
+                name: Attribute::AcpStaged,
+                uuid: UUID_SCHEMA_ATTR_ACP_STAGED,
+                description: String::from("A flag to stage a change of acp_enable. A staged change is evaluated alongside the enforced access controls, and would be denials are logged, but it is not enforced."),
+                multivalue: false,
+                unique: false,
+                phantom: false,
+                sync_allowed: false,
+                replicated: true,
+                index: vec![IndexType::Equality],
+                syntax: SyntaxType::Boolean,
+            },
+        );
 
         self.attributes.insert(
             Attribute::AcpReceiver,
@@ -1880,6 +1900,21 @@ impl SchemaWriteTransaction<'_> {
                 syntax: SyntaxType::Utf8String,
             },
         );
+        self.attributes.insert(
+            Attribute::TelephoneNumber,
+            SchemaAttribute {
+                name: Attribute::TelephoneNumber,
+                uuid: UUID_SCHEMA_ATTR_TELEPHONE_NUMBER,
+                description: String::from("An LDAP Compatible telephoneNumber."),
+                multivalue: true,
+                unique: false,
+                phantom: true,
+                sync_allowed: false,
+                replicated: false,
+                index: vec![],
+                syntax: SyntaxType::Utf8String,
+            },
+        );
         self.attributes.insert(
             Attribute::Uid,
             SchemaAttribute {
@@ -2154,7 +2189,11 @@ impl SchemaWriteTransaction<'_> {
                 name: EntryClass::AccessControlProfile.into(),
                 uuid: UUID_SCHEMA_CLASS_ACCESS_CONTROL_PROFILE,
                 description: String::from("System Access Control Profile Class"),
-                systemmay: vec![Attribute::AcpEnable, Attribute::Description],
+                systemmay: vec![
+                    Attribute::AcpEnable,
+                    Attribute::AcpStaged,
+                    Attribute::Description,
+                ],
                 systemmust: vec![Attribute::Name],
                 systemsupplements: vec![
                     EntryClass::AccessControlSearch.into(),
@@ -76,6 +76,12 @@ fn create_filter_entry<'a>(
         }
     };
 
+    // Scoped api tokens may only modify entries.
+    if ident.api_token_permissions().is_some() {
+        security_access!("denied ❌ - api token permissions do not allow create");
+        return IResult::Denied;
+    }
+
     // Build the set of requested classes and attrs here.
     let create_attrs: BTreeSet<&str> = entry.get_ava_names().collect();
     // If this is empty, we make an empty set, which is fine because
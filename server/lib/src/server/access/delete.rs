@@ -75,6 +75,12 @@ fn delete_filter_entry<'a>(
         }
     };
 
+    // Scoped api tokens may only modify entries.
+    if ident.api_token_permissions().is_some() {
+        security_access!("denied ❌ - api token permissions do not allow delete");
+        return IResult::Denied;
+    }
+
     let ident_memberof = ident.get_memberof();
     let ident_uuid = ident.get_uuid();
 
@@ -49,6 +49,7 @@ const ACP_RESOLVE_FILTER_CACHE_LOCAL: usize = 0;
 mod create;
 mod delete;
 mod modify;
+mod preview;
 pub mod profiles;
 mod search;
 
@@ -130,10 +131,23 @@ struct AccessControlsInner {
     acps_modify: Vec<AccessControlModify>,
     acps_delete: Vec<AccessControlDelete>,
     sync_agreements: HashMap<Uuid, BTreeSet<Attribute>>,
+    // Only present when a change to the access controls is staged.
+    staged: Option<Arc<StagedAccessControls>>,
     // Oauth2
     // Sync prov
 }
 
+/// The access controls as they would be if every staged change to `acp_enable` was applied.
+/// These are evaluated alongside the enforced access controls so that the operations they
+/// would deny can be logged, but they are never enforced.
+#[derive(Clone, Default)]
+pub struct StagedAccessControls {
+    pub(crate) acps_search: Vec<AccessControlSearch>,
+    pub(crate) acps_create: Vec<AccessControlCreate>,
+    pub(crate) acps_modify: Vec<AccessControlModify>,
+    pub(crate) acps_delete: Vec<AccessControlDelete>,
+}
+
 pub struct AccessControls {
     inner: CowCell<AccessControlsInner>,
     // acp_related_search_cache: ARCache<Uuid, Vec<Uuid>>,
@@ -194,6 +208,31 @@ pub trait AccessControlsTransaction<'a> {
     #[allow(clippy::mut_from_ref)]
     fn get_acp_resolve_filter_cache(&self) -> &mut ResolveFilterCacheReadTxn<'a>;
 
+    /// The staged access controls, if a change to the access controls is staged.
+    fn get_staged(&self) -> Option<StagedAccessControlsTransaction<'_, 'a>>;
+
+    /// When a change to the access controls is staged, check if it would deny an operation
+    /// that the enforced access controls allow. This only logs the difference, the staged
+    /// access controls are never enforced.
+    fn staged_would_deny<F>(&self, operation: &str, ident: &Identity, check: F)
+    where
+        F: FnOnce(&StagedAccessControlsTransaction<'_, 'a>) -> Result<bool, OperationError>,
+    {
+        let Some(staged) = self.get_staged() else {
+            return;
+        };
+
+        match debug_span!("access::staged").in_scope(|| check(&staged)) {
+            Ok(true) => {}
+            Ok(false) => security_access!(
+                %ident,
+                %operation,
+                "staged access controls would deny this operation"
+            ),
+            Err(err) => warn!(?err, "Unable to evaluate staged access controls"),
+        }
+    }
+
     #[instrument(level = "trace", name = "access::search_related_acp", skip_all)]
     fn search_related_acp<'b>(
         &'b self,
@@ -236,6 +275,12 @@ pub trait AccessControlsTransaction<'a> {
         let related_acp: Vec<AccessControlSearchResolved<'b>> = search_state
             .iter()
             .filter_map(|acs| {
+                // The white pages may be withheld by the domain policy, such as when
+                // anonymous is outside of the trusted networks.
+                if acs.acp.uuid == UUID_IDM_ACP_WHITE_PAGES_READ && ident.white_pages_denied() {
+                    return None;
+                }
+
                 // Now resolve the receiver filter
                 // Okay, so in filter resolution, the primary error case
                 // is that we have a non-user in the event. We have already
@@ -326,6 +371,27 @@ pub trait AccessControlsTransaction<'a> {
             }
         } else {
             debug!("allowed search of {} entries ✅", allowed_entries.len());
+
+            if let Some(staged) = self.get_staged() {
+                let staged_entries = debug_span!("access::staged").in_scope(|| {
+                    staged.filter_entries(ident, filter_orig, allowed_entries.clone())
+                })?;
+
+                if staged_entries.len() != allowed_entries.len() {
+                    let released: BTreeSet<Uuid> =
+                        staged_entries.iter().map(|e| e.get_uuid()).collect();
+                    let withheld: Vec<Uuid> = allowed_entries
+                        .iter()
+                        .map(|e| e.get_uuid())
+                        .filter(|uuid| !released.contains(uuid))
+                        .collect();
+                    security_access!(
+                        %ident,
+                        ?withheld,
+                        "staged access controls would deny search of these entries"
+                    );
+                }
+            }
         }
 
         Ok(allowed_entries)
@@ -609,6 +675,9 @@ pub trait AccessControlsTransaction<'a> {
 
         if r {
             debug!("allowed modify of {} entries ✅", entries.len());
+            self.staged_would_deny("modify", &me.ident, |staged| {
+                staged.modify_allow_operation(me, entries)
+            });
         } else {
             security_access!("denied ❌ - modify may not proceed");
         }
@@ -739,6 +808,9 @@ pub trait AccessControlsTransaction<'a> {
 
         if r {
             debug!("allowed modify of {} entries ✅", entries.len());
+            self.staged_would_deny("batch modify", &me.ident, |staged| {
+                staged.batch_modify_allow_operation(me, entries)
+            });
         } else {
             security_access!("denied ❌ - modifications may not proceed");
         }
@@ -787,6 +859,9 @@ pub trait AccessControlsTransaction<'a> {
 
         if r {
             debug!("allowed create of {} entries ✅", entries.len());
+            self.staged_would_deny("create", &ce.ident, |staged| {
+                staged.create_allow_operation(ce, entries)
+            });
         } else {
             security_access!("denied ❌ - create may not proceed");
         }
@@ -842,6 +917,9 @@ pub trait AccessControlsTransaction<'a> {
         });
         if r {
             debug!("allowed delete of {} entries ✅", entries.len());
+            self.staged_would_deny("delete", &de.ident, |staged| {
+                staged.delete_allow_operation(de, entries)
+            });
         } else {
             security_access!("denied ❌ - delete may not proceed");
         }
@@ -1016,6 +1094,10 @@ impl AccessControlsWriteTransaction<'_> {
         );
     }
 
+    pub fn update_staged(&mut self, staged: Option<StagedAccessControls>) {
+        self.inner.deref_mut().staged = staged.map(Arc::new);
+    }
+
     pub fn commit(self) -> Result<(), OperationError> {
         self.inner.commit();
 
@@ -1050,6 +1132,17 @@ impl<'a> AccessControlsTransaction<'a> for AccessControlsWriteTransaction<'a> {
             &mut (*mptr) as &mut ResolveFilterCacheReadTxn<'a>
         }
     }
+
+    fn get_staged(&self) -> Option<StagedAccessControlsTransaction<'_, 'a>> {
+        self.inner
+            .staged
+            .as_deref()
+            .map(|staged| StagedAccessControlsTransaction {
+                staged,
+                sync_agreements: &self.inner.sync_agreements,
+                acp_resolve_filter_cache: &self.acp_resolve_filter_cache,
+            })
+    }
 }
 
 // =========================================================================
@@ -1093,6 +1186,58 @@ impl<'a> AccessControlsTransaction<'a> for AccessControlsReadTransaction<'a> {
             &mut (*mptr) as &mut ResolveFilterCacheReadTxn<'a>
         }
     }
+
+    fn get_staged(&self) -> Option<StagedAccessControlsTransaction<'_, 'a>> {
+        self.inner
+            .staged
+            .as_deref()
+            .map(|staged| StagedAccessControlsTransaction {
+                staged,
+                sync_agreements: &self.inner.sync_agreements,
+                acp_resolve_filter_cache: &self.acp_resolve_filter_cache,
+            })
+    }
+}
+
+/// Evaluates the staged access controls within a transaction, sharing its filter cache.
+pub struct StagedAccessControlsTransaction<'t, 'a> {
+    staged: &'t StagedAccessControls,
+    sync_agreements: &'t HashMap<Uuid, BTreeSet<Attribute>>,
+    acp_resolve_filter_cache: &'t Cell<ResolveFilterCacheReadTxn<'a>>,
+}
+
+impl<'a> AccessControlsTransaction<'a> for StagedAccessControlsTransaction<'_, 'a> {
+    fn get_search(&self) -> &Vec<AccessControlSearch> {
+        &self.staged.acps_search
+    }
+
+    fn get_create(&self) -> &Vec<AccessControlCreate> {
+        &self.staged.acps_create
+    }
+
+    fn get_modify(&self) -> &Vec<AccessControlModify> {
+        &self.staged.acps_modify
+    }
+
+    fn get_delete(&self) -> &Vec<AccessControlDelete> {
+        &self.staged.acps_delete
+    }
+
+    fn get_sync_agreements(&self) -> &HashMap<Uuid, BTreeSet<Attribute>> {
+        self.sync_agreements
+    }
+
+    fn get_acp_resolve_filter_cache(&self) -> &mut ResolveFilterCacheReadTxn<'a> {
+        unsafe {
+            let mptr = self.acp_resolve_filter_cache.as_ptr();
+            &mut (*mptr) as &mut ResolveFilterCacheReadTxn<'a>
+        }
+    }
+
+    fn get_staged(&self) -> Option<StagedAccessControlsTransaction<'_, 'a>> {
+        // The staged access controls are the end of the chain.
+        None
+    }
 }
 
 // =========================================================================
@@ -1109,6 +1254,7 @@ impl Default for AccessControls {
                 acps_modify: Vec::with_capacity(0),
                 acps_delete: Vec::with_capacity(0),
                 sync_agreements: HashMap::default(),
+                staged: None,
             }),
             // Allow the expect, if this fails it represents a programming/development
             // failure.
@@ -1153,14 +1299,17 @@ mod tests {
     use uuid::uuid;
 
     use super::{
+        preview::{staged_access_changes, StagedAccessChange},
         profiles::{
             AccessControlCreate, AccessControlDelete, AccessControlModify, AccessControlProfile,
             AccessControlSearch, AccessControlTarget,
         },
         Access, AccessClass, AccessControls, AccessControlsTransaction, AccessEffectivePermission,
+        StagedAccessControls,
     };
     use crate::migration_data::BUILTIN_ACCOUNT_ANONYMOUS;
     use crate::prelude::*;
+    use crate::value::ApiTokenPermission;
     use crate::valueset::ValueSetIname;
 
     const UUID_TEST_ACCOUNT_1: Uuid = uuid::uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930");
@@ -2294,6 +2443,76 @@ mod tests {
         test_acp_modify!(&me_pres_rw, vec![acp_allow], &r_set, true);
     }
 
+    #[test]
+    fn test_access_enforce_api_token_permissions_modify() {
+        let ev1 = E_TESTPERSON_1.clone().into_sealed_committed();
+        let r_set = vec![Arc::new(ev1)];
+
+        let me_pres = |permissions: &[ApiTokenPermission], attr: Attribute, value: Value| {
+            let mut ident = Identity::from_impersonate_entry_readwrite(E_TEST_ACCOUNT_1.clone());
+            ident.restrict_to_api_token_permissions(permissions);
+            ModifyEvent::new_impersonate_identity(
+                ident,
+                filter_all!(f_eq(
+                    Attribute::Name,
+                    PartialValue::new_iname("testperson1")
+                )),
+                ModifyList::new_list(vec![Modify::Present(attr, value)]),
+            )
+        };
+
+        let acp_allow = AccessControlModify::from_raw(
+            "test_modify_allow",
+            Uuid::new_v4(),
+            UUID_TEST_GROUP_1,
+            filter_valid!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("testperson1")
+            )),
+            "name displayname",
+            "name displayname",
+            EntryClass::Account.into(),
+        );
+
+        let permit_name = ApiTokenPermission {
+            class: EntryClass::Person.to_string(),
+            name_prefix: Some("test".to_string()),
+            attrs: btreeset![Attribute::Name],
+        };
+
+        // The permission allows this attribute on this entry.
+        let me = me_pres(
+            &[permit_name.clone()],
+            Attribute::Name,
+            Value::new_iname("value"),
+        );
+        test_acp_modify!(&me, vec![acp_allow.clone()], &r_set, true);
+
+        // The access controls still allow displayname, but the token doesn't.
+        let me = me_pres(
+            &[permit_name.clone()],
+            Attribute::DisplayName,
+            Value::new_utf8s("value"),
+        );
+        test_acp_modify!(&me, vec![acp_allow.clone()], &r_set, false);
+
+        // The name prefix doesn't match the entry.
+        let permit_other = ApiTokenPermission {
+            name_prefix: Some("other".to_string()),
+            ..permit_name.clone()
+        };
+        let me = me_pres(&[permit_other], Attribute::Name, Value::new_iname("value"));
+        test_acp_modify!(&me, vec![acp_allow.clone()], &r_set, false);
+
+        // The class doesn't match the entry.
+        let permit_group = ApiTokenPermission {
+            class: EntryClass::Group.to_string(),
+            ..permit_name
+        };
+        let me = me_pres(&[permit_group], Attribute::Name, Value::new_iname("value"));
+        test_acp_modify!(&me, vec![acp_allow], &r_set, false);
+    }
+
     macro_rules! test_acp_create {
         (
             $ce:expr,
@@ -3424,4 +3643,184 @@ mod tests {
         // Finally test it!
         test_acp_search_reduce!(&se_anon_ro, vec![acp], r_set, ex_anon_some);
     }
+
+    #[qs_test]
+    async fn test_access_delegated_target_group(qs: &QueryServer) {
+        let helpdesk_uuid = Uuid::new_v4();
+        let emea_uuid = Uuid::new_v4();
+        let helper_uuid = Uuid::new_v4();
+        let emea_person_uuid = Uuid::new_v4();
+        let other_person_uuid = Uuid::new_v4();
+        let hp_person_uuid = Uuid::new_v4();
+
+        let person = |name: &str, uuid: Uuid| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (Attribute::DisplayName, Value::new_utf8s(name))
+            )
+        };
+
+        let mut qs_write = qs.write(duration_from_epoch_now()).await.unwrap();
+        assert!(qs_write
+            .internal_create(vec![
+                person("helper", helper_uuid),
+                person("emea_person", emea_person_uuid),
+                person("other_person", other_person_uuid),
+                person("hp_person", hp_person_uuid),
+                entry_init!(
+                    (Attribute::Class, EntryClass::Object.to_value()),
+                    (Attribute::Class, EntryClass::Group.to_value()),
+                    (Attribute::Name, Value::new_iname("helpdesk_emea")),
+                    (Attribute::Uuid, Value::Uuid(helpdesk_uuid)),
+                    (Attribute::Member, Value::Refer(helper_uuid))
+                ),
+                entry_init!(
+                    (Attribute::Class, EntryClass::Object.to_value()),
+                    (Attribute::Class, EntryClass::Group.to_value()),
+                    (Attribute::Name, Value::new_iname("emea_users")),
+                    (Attribute::Uuid, Value::Uuid(emea_uuid)),
+                    (Attribute::Member, Value::Refer(emea_person_uuid)),
+                    (Attribute::Member, Value::Refer(hp_person_uuid))
+                ),
+                entry_init!(
+                    (Attribute::Class, EntryClass::Object.to_value()),
+                    (
+                        Attribute::Class,
+                        EntryClass::AccessControlProfile.to_value()
+                    ),
+                    (Attribute::Class, EntryClass::AccessControlModify.to_value()),
+                    (
+                        Attribute::Class,
+                        EntryClass::AccessControlReceiverGroup.to_value()
+                    ),
+                    (
+                        Attribute::Class,
+                        EntryClass::AccessControlTargetScope.to_value()
+                    ),
+                    (
+                        Attribute::Class,
+                        EntryClass::AccessControlTargetGroup.to_value()
+                    ),
+                    (Attribute::Name, Value::new_iname("acp_helpdesk_emea")),
+                    (Attribute::Uuid, Value::Uuid(Uuid::new_v4())),
+                    (Attribute::AcpReceiverGroup, Value::Refer(helpdesk_uuid)),
+                    (
+                        Attribute::AcpTargetScope,
+                        Value::new_json_filter_s("{\"eq\":[\"class\",\"person\"]}")
+                            .expect("filter")
+                    ),
+                    (Attribute::AcpTargetGroup, Value::Refer(emea_uuid)),
+                    (
+                        Attribute::AcpModifyRemovedAttr,
+                        Value::from(Attribute::Description)
+                    ),
+                    (
+                        Attribute::AcpModifyPresentAttr,
+                        Value::from(Attribute::Description)
+                    )
+                )
+            ])
+            .is_ok());
+
+        // Make one of the members of the target group high privilege.
+        assert!(qs_write
+            .internal_modify_uuid(
+                UUID_IDM_HIGH_PRIVILEGE,
+                &ModifyList::new_append(Attribute::Member, Value::Refer(hp_person_uuid)),
+            )
+            .is_ok());
+        assert!(qs_write.commit().is_ok());
+
+        let mut qs_write = qs.write(duration_from_epoch_now()).await.unwrap();
+        let helper = qs_write
+            .internal_search_uuid(helper_uuid)
+            .map(Identity::from_impersonate_entry_readwrite)
+            .expect("Unable to get helper");
+
+        let modify_description = |qs_write: &mut QueryServerWriteTransaction, target: Uuid| {
+            let filter = filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(target)));
+            qs_write.impersonate_modify(
+                &filter,
+                &filter,
+                &ModifyList::new_purge_and_set(
+                    Attribute::Description,
+                    Value::new_utf8s("delegated"),
+                ),
+                &helper,
+            )
+        };
+
+        // Members of the target group may be managed.
+        assert_eq!(modify_description(&mut qs_write, emea_person_uuid), Ok(()));
+        // Anyone outside of the target group may not.
+        assert_eq!(
+            modify_description(&mut qs_write, other_person_uuid),
+            Err(OperationError::AccessDenied)
+        );
+        // Nor may high privilege members of the target group.
+        assert_eq!(
+            modify_description(&mut qs_write, hp_person_uuid),
+            Err(OperationError::AccessDenied)
+        );
+    }
+
+    #[test]
+    fn test_access_staged_changes() {
+        sketching::test_init();
+
+        let ident = Identity::from_impersonate_entry_readwrite(E_TEST_ACCOUNT_1.clone());
+        let r_set = vec![Arc::new(E_TESTPERSON_1.clone().into_sealed_committed())];
+
+        let search_name = AccessControlSearch::from_raw(
+            "test_acp_name",
+            Uuid::new_v4(),
+            UUID_TEST_GROUP_1,
+            filter_valid!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("testperson1")
+            )),
+            Attribute::Name.as_ref(),
+        );
+        let search_class = AccessControlSearch::from_raw(
+            "test_acp_class",
+            Uuid::new_v4(),
+            UUID_TEST_GROUP_1,
+            filter_valid!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("testperson1")
+            )),
+            Attribute::Class.as_ref(),
+        );
+
+        let ac = AccessControls::default();
+        let mut acw = ac.write();
+        acw.update_search(vec![search_name.clone()])
+            .expect("Failed to update");
+
+        // Nothing is staged, so nothing changes.
+        assert!(staged_access_changes(&acw, &[ident.clone()], &r_set)
+            .expect("Failed to preview")
+            .is_empty());
+
+        // Stage disabling the name profile, and enabling the class profile.
+        acw.update_staged(Some(StagedAccessControls {
+            acps_search: vec![search_class],
+            ..Default::default()
+        }));
+
+        let changes = staged_access_changes(&acw, &[ident], &r_set).expect("Failed to preview");
+        assert_eq!(
+            changes,
+            vec![StagedAccessChange {
+                ident: UUID_TEST_ACCOUNT_1,
+                target: uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930"),
+                gained: BTreeSet::from(["search:class".to_string()]),
+                lost: BTreeSet::from(["search:name".to_string()]),
+            }]
+        );
+    }
 }
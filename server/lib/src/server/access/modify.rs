@@ -33,6 +33,7 @@ pub(super) fn apply_modify_access<'a>(
     let mut allow_rem = BTreeSet::default();
     let mut constrain_cls = BTreeSet::default();
     let mut allow_cls = BTreeSet::default();
+    let mut api_token_constrain: Option<BTreeSet<Attribute>> = None;
 
     // Some useful references.
     //  - needed for checking entry manager conditions.
@@ -67,6 +68,14 @@ pub(super) fn apply_modify_access<'a>(
             AccessResult::Ignore => {}
         }
 
+        // If it's a scoped api token, constrain to what the token permits. This is applied
+        // after the other constraints, as it must further narrow them.
+        match modify_api_token_constrain(ident, entry) {
+            AccessResult::Denied => denied = true,
+            AccessResult::Constrain(set) => api_token_constrain = Some(set),
+            AccessResult::Grant | AccessResult::Allow(_) | AccessResult::Ignore => {}
+        }
+
         // Setup the acp's here
         let scoped_acp: Vec<&AccessControlModify> = related_acp
             .iter()
@@ -168,6 +177,14 @@ pub(super) fn apply_modify_access<'a>(
             allow_rem
         };
 
+        let (allowed_pres, allowed_rem) = match api_token_constrain {
+            Some(api_token_constrain) => (
+                &allowed_pres & &api_token_constrain,
+                &allowed_rem & &api_token_constrain,
+            ),
+            None => (allowed_pres, allowed_rem),
+        };
+
         let allowed_cls = if !constrain_cls.is_empty() {
             // bit_and
             &constrain_cls & &allow_cls
@@ -237,6 +254,26 @@ fn modify_cls_test<'a>(scoped_acp: &[&'a AccessControlModify]) -> AccessResultCl
     AccessResultClass::Allow(allowed_classes)
 }
 
+fn modify_api_token_constrain(ident: &Identity, entry: &Arc<EntrySealedCommitted>) -> AccessResult {
+    let Some(permissions) = ident.api_token_permissions() else {
+        return AccessResult::Ignore;
+    };
+
+    let allowed: BTreeSet<Attribute> = permissions
+        .iter()
+        .filter(|permission| permission.entry_match(entry))
+        .flat_map(|permission| permission.attrs.iter().cloned())
+        .collect();
+
+    // An empty constraint would not restrict anything, so this must be denied instead.
+    if allowed.is_empty() {
+        security_access!(entry = ?entry.get_display_id(), "denied ❌ - api token permissions do not allow modifying this entry");
+        AccessResult::Denied
+    } else {
+        AccessResult::Constrain(allowed)
+    }
+}
+
 fn modify_sync_constrain(
     ident: &Identity,
     entry: &Arc<EntrySealedCommitted>,
@@ -0,0 +1,170 @@
+//! Preview how a staged change to the access controls would affect existing accounts.
+//!
+//! A change to `acp_enable` can be staged with `acp_staged`. The staged access controls are
+//! evaluated alongside the enforced ones, but are never enforced. This compares the effective
+//! permissions of a sample of accounts on a sample of entries between the two, so that an
+//! administrator can see who gains or loses rights before the change is enforced.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use kanidm_proto::internal::{AccessControlPreview, AccessControlPreviewChange};
+use rand::prelude::*;
+
+use super::{Access, AccessClass, AccessControlsTransaction, AccessEffectivePermission};
+use crate::prelude::*;
+
+/// The difference in the rights of an identity on an entry.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct StagedAccessChange {
+    pub(crate) ident: Uuid,
+    pub(crate) target: Uuid,
+    pub(crate) gained: BTreeSet<String>,
+    pub(crate) lost: BTreeSet<String>,
+}
+
+fn access_rights(operation: &str, access: &Access) -> BTreeSet<String> {
+    match access {
+        Access::Denied => BTreeSet::new(),
+        Access::Grant => BTreeSet::from([format!("{operation}:*")]),
+        Access::Allow(attrs) => attrs
+            .iter()
+            .map(|attr| format!("{operation}:{attr}"))
+            .collect(),
+    }
+}
+
+impl AccessEffectivePermission {
+    /// The rights this grants, described as `<operation>:<attribute>`.
+    fn rights(&self) -> BTreeSet<String> {
+        let mut rights = access_rights("search", &self.search);
+        rights.extend(access_rights("modify_present", &self.modify_pres));
+        rights.extend(access_rights("modify_removed", &self.modify_rem));
+        match &self.modify_class {
+            AccessClass::Denied => {}
+            AccessClass::Grant => {
+                rights.insert("modify_class:*".to_string());
+            }
+            AccessClass::Allow(classes) => {
+                rights.extend(classes.iter().map(|class| format!("modify_class:{class}")));
+            }
+        }
+        if self.delete {
+            rights.insert("delete".to_string());
+        }
+        rights
+    }
+}
+
+/// Compare the effective permissions of each identity on the entries, between the enforced
+/// access controls and the staged ones. Only the pairs where they differ are returned.
+pub(crate) fn staged_access_changes<'a, T: AccessControlsTransaction<'a>>(
+    access: &T,
+    idents: &[Identity],
+    entries: &[Arc<EntrySealedCommitted>],
+) -> Result<Vec<StagedAccessChange>, OperationError> {
+    let Some(staged) = access.get_staged() else {
+        return Ok(Vec::with_capacity(0));
+    };
+
+    let mut changes = Vec::new();
+
+    for ident in idents {
+        let enforced = access.effective_permission_check(ident, None, entries)?;
+        let candidate = staged.effective_permission_check(ident, None, entries)?;
+
+        for (enforced, candidate) in enforced.iter().zip(candidate.iter()) {
+            let enforced_rights = enforced.rights();
+            let candidate_rights = candidate.rights();
+
+            if enforced_rights != candidate_rights {
+                changes.push(StagedAccessChange {
+                    ident: enforced.ident,
+                    target: enforced.target,
+                    gained: candidate_rights
+                        .difference(&enforced_rights)
+                        .cloned()
+                        .collect(),
+                    lost: enforced_rights
+                        .difference(&candidate_rights)
+                        .cloned()
+                        .collect(),
+                });
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+impl QueryServerReadTransaction<'_> {
+    /// Preview the staged change to the access controls, for a random sample of up to
+    /// `sample_size` accounts on up to `sample_size` entries.
+    #[instrument(level = "debug", skip_all)]
+    pub fn access_control_preview(
+        &mut self,
+        sample_size: usize,
+    ) -> Result<AccessControlPreview, OperationError> {
+        let staged = self.internal_search(filter!(f_and!([
+            f_eq(Attribute::Class, EntryClass::AccessControlProfile.into()),
+            f_eq(Attribute::AcpStaged, PartialValue::new_bool(true)),
+        ])))?;
+
+        let staged: Vec<String> = staged
+            .iter()
+            .filter_map(|e| e.get_ava_single_iname(Attribute::Name).map(str::to_string))
+            .collect();
+
+        if staged.is_empty() {
+            return Ok(AccessControlPreview::default());
+        }
+
+        let accounts =
+            self.internal_search(filter!(f_eq(Attribute::Class, EntryClass::Account.into())))?;
+        let entries = self.internal_search(filter!(f_pres(Attribute::Class)))?;
+
+        let mut rng = thread_rng();
+        let accounts: Vec<_> = accounts
+            .choose_multiple(&mut rng, sample_size)
+            .cloned()
+            .collect();
+        let entries: Vec<_> = entries
+            .choose_multiple(&mut rng, sample_size)
+            .cloned()
+            .collect();
+
+        let idents: Vec<_> = accounts
+            .iter()
+            .cloned()
+            .map(Identity::from_access_preview)
+            .collect();
+
+        let changes = staged_access_changes(self.get_accesscontrols(), &idents, &entries)?;
+
+        let display_id = |uuid: Uuid| {
+            accounts
+                .iter()
+                .chain(entries.iter())
+                .find(|e| e.get_uuid() == uuid)
+                .map(|e| e.get_display_id())
+                .unwrap_or_else(|| uuid.to_string())
+        };
+
+        let changes = changes
+            .into_iter()
+            .map(|change| AccessControlPreviewChange {
+                account: display_id(change.ident),
+                target: display_id(change.target),
+                gained: change.gained.into_iter().collect(),
+                lost: change.lost.into_iter().collect(),
+            })
+            .collect();
+
+        Ok(AccessControlPreview {
+            staged,
+            accounts_sampled: accounts.len() as u64,
+            entries_sampled: entries.len() as u64,
+            changes,
+        })
+    }
+}
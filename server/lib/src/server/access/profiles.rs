@@ -401,10 +401,7 @@ impl AccessControlTargetCondition {
 #[derive(Debug, Clone)]
 pub struct AccessControlProfile {
     pub name: String,
-    // Currently we retrieve this but don't use it. We could depending on how we change
-    // the acp update routine.
-    #[allow(dead_code)]
-    uuid: Uuid,
+    pub(super) uuid: Uuid,
     pub receiver: AccessControlReceiver,
     pub target: AccessControlTarget,
 }
@@ -461,22 +458,72 @@ impl AccessControlProfile {
             AccessControlReceiver::None
         };
 
-        let target = if value.attribute_equality(
+        let target_scope = if value.attribute_equality(
             Attribute::Class,
             &EntryClass::AccessControlTargetScope.into(),
         ) {
-            // targetscope, and turn to real filter
-            let targetscope_f: ProtoFilter = value
+            value
                 .get_ava_single_protofilter(Attribute::AcpTargetScope)
                 .cloned()
+                .map(Some)
                 .ok_or_else(|| {
                     admin_error!("Missing {}", Attribute::AcpTargetScope);
                     OperationError::InvalidAcpState(format!(
                         "Missing {}",
                         Attribute::AcpTargetScope
                     ))
-                })?;
+                })?
+        } else {
+            None
+        };
+
+        let target_groups = if value.attribute_equality(
+            Attribute::Class,
+            &EntryClass::AccessControlTargetGroup.into(),
+        ) {
+            value
+                .get_ava_refer(Attribute::AcpTargetGroup)
+                .map(Some)
+                .ok_or_else(|| {
+                    admin_error!("Missing {}", Attribute::AcpTargetGroup);
+                    OperationError::InvalidAcpState(format!(
+                        "Missing {}",
+                        Attribute::AcpTargetGroup
+                    ))
+                })?
+        } else {
+            None
+        };
+
+        let targetscope_f = match (target_scope, target_groups) {
+            (target_scope, Some(target_groups)) => {
+                // A delegated scope is limited to the members of the target groups. It may never
+                // extend to high privilege entries, else a delegated administrator who can manage
+                // the membership of a target group could take over a privileged account.
+                let members = ProtoFilter::Or(
+                    target_groups
+                        .iter()
+                        .map(|group| {
+                            ProtoFilter::Eq(Attribute::MemberOf.to_string(), group.to_string())
+                        })
+                        .collect(),
+                );
+                let not_high_privilege = ProtoFilter::AndNot(Box::new(ProtoFilter::Eq(
+                    Attribute::MemberOf.to_string(),
+                    UUID_IDM_HIGH_PRIVILEGE.to_string(),
+                )));
+
+                Some(ProtoFilter::And(
+                    target_scope
+                        .into_iter()
+                        .chain([members, not_high_privilege])
+                        .collect(),
+                ))
+            }
+            (target_scope, None) => target_scope,
+        };
 
+        let target = if let Some(targetscope_f) = targetscope_f {
             let ident = Identity::from_internal();
 
             let targetscope_i = Filter::from_rw(&ident, &targetscope_f, qs).map_err(|e| {
@@ -187,7 +187,19 @@ fn search_oauth2_filter_entry(ident: &Identity, entry: &Arc<EntrySealedCommitted
                 .map(|(maps, mo)| maps.keys().any(|k| mo.contains(k)))
                 .unwrap_or(false);
 
-            if contains_o2_rs && contains_o2_scope_member {
+            // If the client has assigned members, only they may see it.
+            let is_o2_assigned = entry
+                .get_ava_refer(Attribute::OAuth2RsAssignedMember)
+                .map(|assigned| {
+                    assigned.contains(&iuser.entry.get_uuid())
+                        || ident
+                            .get_memberof()
+                            .map(|mo| !mo.is_disjoint(assigned))
+                            .unwrap_or(false)
+                })
+                .unwrap_or(true);
+
+            if contains_o2_rs && contains_o2_scope_member && is_o2_assigned {
                 security_debug!(entry = ?entry.get_uuid(), ident = ?iuser.entry.get_uuid2rdn(), "ident is a memberof a group granted an oauth2 scope by this entry");
 
                 return AccessResult::Allow(btreeset!(
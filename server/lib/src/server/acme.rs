@@ -0,0 +1,157 @@
+//! Storage of the account and certificate of the ACME client of this server. These are local
+//! to this server and are not replicated, since each server proves control of its own names.
+
+use crate::be::keystorage::{KeyHandle, KeyHandleId};
+use crate::prelude::*;
+use kanidm_lib_crypto::prelude::{PKey, Private, X509};
+
+impl QueryServerWriteTransaction<'_> {
+    /// The key of the ACME account of this server, and the url of the account if it has been
+    /// registered.
+    pub fn acme_account(
+        &mut self,
+    ) -> Result<Option<(PKey<Private>, Option<String>)>, OperationError> {
+        let maybe_key_handle = self
+            .get_be_txn()
+            .get_key_handle(KeyHandleId::AcmeAccount)
+            .map_err(|err| {
+                error!(?err, "Unable to access acme account key");
+                err
+            })?;
+
+        match maybe_key_handle {
+            Some(KeyHandle::AcmeAccount { private, url }) => Ok(Some((private, url))),
+            Some(_) => {
+                error!("ACME account key handle contains an unexpected key type");
+                Err(OperationError::InvalidState)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_acme_account(
+        &mut self,
+        private: PKey<Private>,
+        url: Option<String>,
+    ) -> Result<(), OperationError> {
+        self.get_be_txn()
+            .set_key_handle(
+                KeyHandleId::AcmeAccount,
+                KeyHandle::AcmeAccount { private, url },
+            )
+            .map_err(|err| {
+                error!(?err, "Unable to persist acme account key");
+                err
+            })
+    }
+
+    /// The certificate that was last issued to this server by the ACME client, and its key.
+    /// The chain has the leaf certificate first.
+    pub fn acme_certificate(
+        &mut self,
+    ) -> Result<Option<(PKey<Private>, Vec<X509>)>, OperationError> {
+        let maybe_key_handle = self
+            .get_be_txn()
+            .get_key_handle(KeyHandleId::AcmeCertificate)
+            .map_err(|err| {
+                error!(?err, "Unable to access acme certificate");
+                err
+            })?;
+
+        match maybe_key_handle {
+            Some(KeyHandle::X509Chain { private, chain }) => chain
+                .iter()
+                .map(|der| X509::from_der(der.as_slice()))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|chain| Some((private, chain)))
+                .map_err(|err| {
+                    error!(?err, "Unable to decode acme certificate chain");
+                    OperationError::CryptographyError
+                }),
+            Some(_) => {
+                error!("ACME certificate key handle contains an unexpected key type");
+                Err(OperationError::InvalidState)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_acme_certificate(
+        &mut self,
+        private: PKey<Private>,
+        chain: &[X509],
+    ) -> Result<(), OperationError> {
+        let chain = chain
+            .iter()
+            .map(|x509| x509.to_der())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| {
+                error!(?err, "Unable to encode acme certificate chain");
+                OperationError::CryptographyError
+            })?;
+
+        self.get_be_txn()
+            .set_key_handle(
+                KeyHandleId::AcmeCertificate,
+                KeyHandle::X509Chain { private, chain },
+            )
+            .map_err(|err| {
+                error!(?err, "Unable to persist acme certificate");
+                err
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use kanidm_lib_crypto::mtls::build_self_signed_server_and_client_identity;
+
+    #[qs_test]
+    async fn test_acme_storage(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let mut server_txn = server.write(ct).await.unwrap();
+
+        assert!(server_txn.acme_account().unwrap().is_none());
+        assert!(server_txn.acme_certificate().unwrap().is_none());
+
+        let (private, x509) =
+            build_self_signed_server_and_client_identity(Uuid::new_v4(), "example.com", 1)
+                .expect("Unable to build identity");
+
+        server_txn
+            .set_acme_account(private.clone(), None)
+            .expect("Unable to store account");
+        server_txn
+            .set_acme_account(
+                private.clone(),
+                Some("https://acme.example.com/acct/1".to_string()),
+            )
+            .expect("Unable to store account");
+
+        server_txn
+            .set_acme_certificate(private.clone(), &[x509.clone(), x509.clone()])
+            .expect("Unable to store certificate");
+
+        server_txn.commit().expect("Unable to commit");
+
+        let mut server_txn = server.write(ct).await.unwrap();
+
+        let (account_key, url) = server_txn
+            .acme_account()
+            .unwrap()
+            .expect("Account not found");
+        assert!(account_key.public_eq(&private));
+        assert_eq!(url.as_deref(), Some("https://acme.example.com/acct/1"));
+
+        let (cert_key, chain) = server_txn
+            .acme_certificate()
+            .unwrap()
+            .expect("Certificate not found");
+        assert!(cert_key.public_eq(&private));
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[0].to_der().unwrap(), x509.to_der().unwrap());
+
+        server_txn.commit().expect("Unable to commit");
+    }
+}
@@ -0,0 +1,290 @@
+//! Inspection and management of the server for the local admin socket, where automation on the
+//! host can manage the server without network credentials.
+
+use crate::prelude::*;
+
+use crate::be::BackendTransaction;
+use crate::repl::ruv::ReplicationUpdateVectorTransaction;
+use crate::schema::SchemaTransaction;
+use kanidm_proto::internal::{
+    KeyObjectReport, KeyReport, ReplicationServerStatus, ReplicationStatusReport,
+    SchemaAttributeReport, SchemaClassReport, SchemaReport,
+};
+
+impl QueryServerReadTransaction<'_> {
+    /// Report the schema that is currently loaded.
+    pub fn schema_report(&mut self) -> Result<SchemaReport, OperationError> {
+        let schema = self.get_schema();
+
+        let mut attributes: Vec<_> = schema
+            .get_attributes()
+            .values()
+            .map(|attr| SchemaAttributeReport {
+                name: attr.name.to_string(),
+                uuid: attr.uuid,
+                description: attr.description.clone(),
+                syntax: attr.syntax.to_string(),
+                multivalue: attr.multivalue,
+                unique: attr.unique,
+                index: attr.index.iter().map(|idx| idx.to_string()).collect(),
+            })
+            .collect();
+        attributes.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+        let mut classes: Vec<_> = schema
+            .get_classes()
+            .values()
+            .map(|class| SchemaClassReport {
+                name: class.name.to_string(),
+                uuid: class.uuid,
+                description: class.description.clone(),
+                must: class
+                    .systemmust
+                    .iter()
+                    .chain(class.must.iter())
+                    .map(|attr| attr.to_string())
+                    .collect(),
+                may: class
+                    .systemmay
+                    .iter()
+                    .chain(class.may.iter())
+                    .map(|attr| attr.to_string())
+                    .collect(),
+            })
+            .collect();
+        classes.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(SchemaReport {
+            attributes,
+            classes,
+        })
+    }
+
+    /// Report the key objects of this domain and the keys they hold. Private key material is
+    /// never included.
+    pub fn key_object_report(&mut self) -> Result<Vec<KeyObjectReport>, OperationError> {
+        let filt = filter!(f_eq(Attribute::Class, EntryClass::KeyObject.into()));
+
+        let entries = self.internal_search(filt)?;
+
+        Ok(entries
+            .iter()
+            .map(|entry| {
+                let keys = entry
+                    .get_ava_set(Attribute::KeyInternalData)
+                    .and_then(|vs| vs.as_key_internal_map())
+                    .map(|key_map| {
+                        key_map
+                            .iter()
+                            .map(|(key_id, key_data)| KeyReport {
+                                key_id: key_id.clone(),
+                                usage: key_data.usage.to_string(),
+                                status: key_data.status.to_string(),
+                                valid_from: key_data.valid_from,
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                KeyObjectReport {
+                    uuid: entry.get_uuid(),
+                    name: entry
+                        .get_ava_single_proto_string(Attribute::Spn)
+                        .or_else(|| entry.get_ava_single_proto_string(Attribute::Name)),
+                    keys,
+                }
+            })
+            .collect())
+    }
+
+    /// Report the range of changes held from each server, based on the replication update
+    /// vector of this server.
+    pub fn replication_status(&mut self) -> Result<ReplicationStatusReport, OperationError> {
+        let local_s_uuid = self.trim_cid().s_uuid;
+        let trim_point = self.trim_cid().ts.as_secs();
+
+        let ruv_range = self.get_be_txn().get_ruv().current_ruv_range()?;
+
+        let servers = ruv_range
+            .iter()
+            .map(|(s_uuid, range)| ReplicationServerStatus {
+                server_uuid: *s_uuid,
+                local: *s_uuid == local_s_uuid,
+                oldest_change: range.ts_min.as_secs(),
+                newest_change: range.ts_max.as_secs(),
+            })
+            .collect();
+
+        Ok(ReplicationStatusReport {
+            trim_point,
+            servers,
+        })
+    }
+}
+
+impl QueryServerWriteTransaction<'_> {
+    fn key_object_uuid(&mut self, key_object: &str) -> Result<Uuid, OperationError> {
+        let target_uuid = self.name_to_uuid(key_object)?;
+
+        let entry = self.internal_search_uuid(target_uuid)?;
+
+        if entry.attribute_equality(Attribute::Class, &EntryClass::KeyObject.into()) {
+            Ok(target_uuid)
+        } else {
+            error!(%key_object, "entry is not a key object");
+            Err(OperationError::InvalidEntryState)
+        }
+    }
+
+    /// Rotate the keys of this key object. The new keys are used immediately, and the
+    /// former keys remain valid for verification until they are revoked.
+    pub fn key_object_rotate(
+        &mut self,
+        key_object: &str,
+        current_time: Duration,
+    ) -> Result<(), OperationError> {
+        let target_uuid = self.key_object_uuid(key_object)?;
+
+        // Rotation only applies to times after the time of this transaction.
+        let rotation_time = current_time + Duration::from_secs(1);
+
+        self.internal_modify_uuid(
+            target_uuid,
+            &ModifyList::new_append(
+                Attribute::KeyActionRotate,
+                Value::new_datetime_epoch(rotation_time),
+            ),
+        )
+    }
+
+    /// Revoke a key of this key object. Anything signed by this key is no longer valid.
+    pub fn key_object_revoke(
+        &mut self,
+        key_object: &str,
+        key_id: &str,
+    ) -> Result<(), OperationError> {
+        let target_uuid = self.key_object_uuid(key_object)?;
+
+        let key_id = key_id.to_lowercase();
+
+        let key_exists = self
+            .internal_search_uuid(target_uuid)?
+            .get_ava_set(Attribute::KeyInternalData)
+            .and_then(|vs| vs.as_key_internal_map())
+            .is_some_and(|key_map| key_map.contains_key(&key_id));
+
+        if !key_exists {
+            error!(%key_object, %key_id, "key object does not hold this key");
+            return Err(OperationError::NoMatchingEntries);
+        }
+
+        self.internal_modify_uuid(
+            target_uuid,
+            &ModifyList::new_append(Attribute::KeyActionRevoke, Value::HexString(key_id)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::value::KeyStatus;
+
+    #[qs_test]
+    async fn test_admin_schema_report(server: &QueryServer) {
+        let mut read_txn = server.read().await.unwrap();
+        let report = read_txn.schema_report().expect("Unable to report schema");
+
+        let name = report
+            .attributes
+            .iter()
+            .find(|attr| attr.name == Attribute::Name.as_str())
+            .expect("name attribute not found");
+        assert!(name.unique);
+        assert!(!name.multivalue);
+
+        assert!(report
+            .classes
+            .iter()
+            .any(|class| class.name == EntryClass::Person.as_ref()));
+        assert!(report
+            .attributes
+            .windows(2)
+            .all(|pair| pair[0].name <= pair[1].name));
+    }
+
+    #[qs_test]
+    async fn test_admin_replication_status(server: &QueryServer) {
+        let mut read_txn = server.read().await.unwrap();
+        let report = read_txn
+            .replication_status()
+            .expect("Unable to report replication status");
+
+        let local: Vec<_> = report.servers.iter().filter(|s| s.local).collect();
+        assert_eq!(local.len(), 1);
+        assert!(local[0].oldest_change <= local[0].newest_change);
+    }
+
+    #[qs_test]
+    async fn test_admin_key_object_rotate_revoke(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let domain_uuid = UUID_DOMAIN_INFO.to_string();
+
+        let valid_keys = |server_txn: &mut QueryServerWriteTransaction| {
+            server_txn
+                .internal_search_uuid(UUID_DOMAIN_INFO)
+                .expect("Unable to access domain")
+                .get_ava_set(Attribute::KeyInternalData)
+                .and_then(|vs| vs.as_key_internal_map())
+                .map(|key_map| {
+                    key_map
+                        .iter()
+                        .filter(|(_, key_data)| key_data.status == KeyStatus::Valid)
+                        .map(|(key_id, _)| key_id.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut server_txn = server.write(ct).await.unwrap();
+
+        let keys_before = valid_keys(&mut server_txn);
+        assert!(!keys_before.is_empty());
+
+        server_txn
+            .key_object_rotate(&domain_uuid, ct)
+            .expect("Unable to rotate keys");
+        let keys_after = valid_keys(&mut server_txn);
+        assert!(keys_after.len() > keys_before.len());
+
+        let revoke_id = keys_before[0].clone();
+        server_txn
+            .key_object_revoke(&domain_uuid, &revoke_id)
+            .expect("Unable to revoke key");
+        assert!(!valid_keys(&mut server_txn).contains(&revoke_id));
+
+        // Unknown keys and entries that are not key objects are rejected.
+        assert_eq!(
+            server_txn.key_object_revoke(&domain_uuid, "00ff"),
+            Err(OperationError::NoMatchingEntries)
+        );
+        assert_eq!(
+            server_txn.key_object_rotate("admin", ct),
+            Err(OperationError::InvalidEntryState)
+        );
+
+        server_txn.commit().expect("Unable to commit");
+
+        let mut read_txn = server.read().await.unwrap();
+        let report = read_txn
+            .key_object_report()
+            .expect("Unable to report key objects");
+        assert!(report.iter().any(|key_object| {
+            key_object.uuid == UUID_DOMAIN_INFO
+                && key_object
+                    .keys
+                    .iter()
+                    .any(|key| key.key_id == revoke_id && key.status == "revoked")
+        }));
+    }
+}
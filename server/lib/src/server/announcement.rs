@@ -0,0 +1,128 @@
+//! Announcements are timed messages from the administrators, such as maintenance windows or
+//! phishing warnings. They are held with the domain info so that every view can show them
+//! without a database transaction, and reloaded whenever an announcement changes.
+
+use std::str::FromStr;
+
+use kanidm_proto::internal::{Announcement, AnnouncementSeverity};
+
+use crate::prelude::*;
+
+fn announcement_from_entry(entry: &EntrySealedCommitted) -> Option<Announcement> {
+    let message = entry
+        .get_ava_single_utf8(Attribute::AnnouncementMessage)?
+        .to_string();
+
+    // This is validated by the announcement plugin.
+    let severity = entry
+        .get_ava_single_iutf8(Attribute::AnnouncementSeverity)
+        .and_then(|severity| AnnouncementSeverity::from_str(severity).ok())
+        .unwrap_or_default();
+
+    Some(Announcement {
+        uuid: entry.get_uuid(),
+        message,
+        severity,
+        start: entry.get_ava_single_datetime(Attribute::AnnouncementStart),
+        end: entry.get_ava_single_datetime(Attribute::AnnouncementEnd),
+        cli: entry
+            .get_ava_single_bool(Attribute::AnnouncementCli)
+            .unwrap_or_default(),
+    })
+}
+
+impl QueryServerWriteTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) fn reload_announcements(&mut self) -> Result<(), OperationError> {
+        let entries = self.internal_search(filter!(f_eq(
+            Attribute::Class,
+            EntryClass::Announcement.into()
+        )))?;
+
+        let announcements = entries
+            .iter()
+            .filter_map(|entry| announcement_from_entry(entry))
+            .collect();
+
+        self.d_info.get_mut().d_announcements = announcements;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kanidm_proto::internal::AnnouncementSeverity;
+
+    use crate::prelude::*;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[qs_test]
+    async fn test_announcements_reload(server: &QueryServer) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut server_txn = server.write(ct).await.unwrap();
+        let future_uuid = Uuid::new_v4();
+
+        let current = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Announcement.to_value()),
+            (Attribute::Name, Value::new_iname("maintenance")),
+            (
+                Attribute::AnnouncementMessage,
+                Value::new_utf8s("Maintenance is in progress")
+            ),
+            (Attribute::AnnouncementSeverity, Value::new_iutf8("warning")),
+            (
+                Attribute::AnnouncementEnd,
+                Value::new_datetime_epoch(ct + Duration::from_secs(3600))
+            )
+        );
+
+        let future = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Announcement.to_value()),
+            (Attribute::Name, Value::new_iname("upgrade")),
+            (Attribute::Uuid, Value::Uuid(future_uuid)),
+            (
+                Attribute::AnnouncementMessage,
+                Value::new_utf8s("An upgrade is planned")
+            ),
+            (
+                Attribute::AnnouncementStart,
+                Value::new_datetime_epoch(ct + Duration::from_secs(60))
+            )
+        );
+
+        assert!(server_txn.internal_create(vec![current, future]).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let d_info = server.d_info.read();
+
+        let announcements = d_info.announcements(ct);
+        assert_eq!(announcements.len(), 1);
+        assert_eq!(announcements[0].message, "Maintenance is in progress");
+        assert_eq!(announcements[0].severity, AnnouncementSeverity::Warning);
+
+        // Both are shown once the second has started, the most severe first.
+        let announcements = d_info.announcements(ct + Duration::from_secs(120));
+        assert_eq!(announcements.len(), 2);
+        assert_eq!(announcements[0].severity, AnnouncementSeverity::Warning);
+        assert_eq!(announcements[1].severity, AnnouncementSeverity::Info);
+
+        // The first is no longer shown after it ends.
+        let announcements = d_info.announcements(ct + Duration::from_secs(3600));
+        assert_eq!(announcements.len(), 1);
+        assert_eq!(announcements[0].message, "An upgrade is planned");
+        drop(d_info);
+
+        // Deleting an announcement removes it.
+        let mut server_txn = server.write(ct).await.unwrap();
+        assert!(server_txn.internal_delete_uuid(future_uuid).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let d_info = server.d_info.read();
+        assert!(d_info
+            .announcements(ct + Duration::from_secs(3600))
+            .is_empty());
+    }
+}
@@ -0,0 +1,365 @@
+//! Create or update entries so that they match a description, and delete them only if they
+//! are unchanged. These allow tools that manage entries declaratively to avoid racing with
+//! other changes between reading an entry and writing it.
+//!
+//! The version of an entry is the change id of its last modification, which is returned in
+//! the http api as the entry's ETag and checked against an If-Match header within the same
+//! transaction as the write.
+
+use super::QueryServerWriteTransaction;
+use crate::prelude::*;
+use crate::valueset;
+use kanidm_proto::internal::{Modify as ProtoModify, ModifyList as ProtoModifyList};
+use kanidm_proto::v1::{Entry as ProtoEntry, EntryApplyResponse};
+
+/// The version of an entry, which changes each time that the entry is modified.
+pub fn entry_version<VALID, STATE>(entry: &Entry<VALID, STATE>) -> Option<String> {
+    entry
+        .get_ava_set(Attribute::LastModifiedCid)
+        .and_then(|vs| vs.as_cid_set())
+        .and_then(|cids| cids.iter().next())
+        .map(|cid| cid.to_string())
+}
+
+impl QueryServerWriteTransaction<'_> {
+    fn apply_search_id(
+        &mut self,
+        ident: &Identity,
+        id: &str,
+        filter: &Filter<FilterInvalid>,
+    ) -> Result<Option<EntryReducedCommitted>, OperationError> {
+        let f_id = Filter::join_parts_and(filter.clone(), filter_all!(f_id(id)));
+        self.impersonate_search_ext(f_id.clone(), f_id, ident)
+            .map(|mut entries| entries.pop())
+    }
+
+    fn apply_version(&mut self, uuid: Uuid) -> Result<String, OperationError> {
+        self.internal_search_uuid(uuid)
+            .ok()
+            .and_then(|entry| entry_version(entry.as_ref()))
+            .ok_or(OperationError::InvalidEntryState)
+    }
+
+    /// Compare the values of an attribute with the values that the identity is able to read.
+    /// Attributes that can't be read are always considered to differ, so that nothing is
+    /// revealed about their values.
+    fn apply_values_equal(
+        &mut self,
+        current: &EntryReducedCommitted,
+        attr: &Attribute,
+        values: &[String],
+    ) -> bool {
+        let Some(current) = current.get_ava_set(attr) else {
+            return values.is_empty();
+        };
+
+        values
+            .iter()
+            .map(|value| self.clone_value(attr, value))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|desired| valueset::from_value_iter(desired.into_iter()))
+            .map(|desired| &desired == current)
+            .unwrap_or(false)
+    }
+
+    /// Create the entry if it doesn't exist, otherwise replace the values of the attributes
+    /// in the description that differ from the entry. Attributes that are not described are
+    /// left unchanged, and the classes of the entry are only set when it's created. If the
+    /// entry is created with an id that is a uuid, that is the uuid of the entry.
+    ///
+    /// If a version is given the entry must exist and be unchanged since that version. In a
+    /// dry run the response describes what would change, and the caller must not commit.
+    #[instrument(level = "debug", skip_all)]
+    pub fn apply_entry(
+        &mut self,
+        ident: &Identity,
+        id: &str,
+        filter: Filter<FilterInvalid>,
+        entry: &ProtoEntry,
+        if_match: Option<&str>,
+        dry_run: bool,
+    ) -> Result<EntryApplyResponse, OperationError> {
+        let Some(current) = self.apply_search_id(ident, id, &filter)? else {
+            if if_match.is_some() {
+                return Err(OperationError::HT0005EntryVersionMismatch);
+            }
+            return self.apply_entry_create(ident, id, entry, dry_run);
+        };
+
+        let target_uuid = current.get_uuid();
+        let version = self.apply_version(target_uuid)?;
+
+        if if_match.is_some_and(|expected| expected != version) {
+            return Err(OperationError::HT0005EntryVersionMismatch);
+        }
+
+        let mut modified = Vec::new();
+        let mut mods = Vec::new();
+
+        for (attr, values) in entry.attrs.iter() {
+            let attr_n = Attribute::from(attr.as_str());
+            if attr_n == Attribute::Class || self.apply_values_equal(&current, &attr_n, values) {
+                continue;
+            }
+
+            modified.push(attr.clone());
+            mods.push(ProtoModify::Purged(attr.clone()));
+            mods.extend(
+                values
+                    .iter()
+                    .map(|value| ProtoModify::Present(attr.clone(), value.clone())),
+            );
+        }
+
+        if mods.is_empty() {
+            return Ok(EntryApplyResponse {
+                uuid: target_uuid,
+                created: false,
+                modified,
+                version: Some(version),
+                dry_run,
+            });
+        }
+
+        let me = ModifyEvent::from_parts(
+            ident.clone(),
+            target_uuid,
+            &ProtoModifyList::new_list(mods),
+            filter,
+            self,
+        )?;
+        self.modify(&me)?;
+
+        let version = if dry_run {
+            version
+        } else {
+            self.apply_version(target_uuid)?
+        };
+
+        Ok(EntryApplyResponse {
+            uuid: target_uuid,
+            created: false,
+            modified,
+            version: Some(version),
+            dry_run,
+        })
+    }
+
+    fn apply_entry_create(
+        &mut self,
+        ident: &Identity,
+        id: &str,
+        entry: &ProtoEntry,
+        dry_run: bool,
+    ) -> Result<EntryApplyResponse, OperationError> {
+        let mut attrs = entry.attrs.clone();
+
+        let uuid = match Uuid::parse_str(id) {
+            Ok(uuid) => {
+                attrs
+                    .entry(ATTR_UUID.to_string())
+                    .or_insert_with(|| vec![uuid.to_string()]);
+                uuid
+            }
+            Err(_) => {
+                attrs
+                    .entry(ATTR_NAME.to_string())
+                    .or_insert_with(|| vec![id.to_string()]);
+                match attrs.get(ATTR_UUID).and_then(|values| values.first()) {
+                    Some(uuid) => Uuid::parse_str(uuid).map_err(|_| {
+                        OperationError::InvalidAttribute("Invalid uuid".to_string())
+                    })?,
+                    None => {
+                        let uuid = Uuid::new_v4();
+                        attrs.insert(ATTR_UUID.to_string(), vec![uuid.to_string()]);
+                        uuid
+                    }
+                }
+            }
+        };
+
+        let modified = attrs
+            .keys()
+            .filter(|attr| attr.as_str() != ATTR_CLASS)
+            .cloned()
+            .collect();
+
+        let candidate = Entry::from_proto_entry(&ProtoEntry { attrs }, self)?;
+
+        let ce = CreateEvent {
+            ident: ident.clone(),
+            entries: vec![candidate],
+        };
+        self.create(&ce)?;
+
+        let version = if dry_run {
+            None
+        } else {
+            Some(self.apply_version(uuid)?)
+        };
+
+        Ok(EntryApplyResponse {
+            uuid,
+            created: true,
+            modified,
+            version,
+            dry_run,
+        })
+    }
+
+    /// Delete the entry, only if it is unchanged since the version that is given.
+    #[instrument(level = "debug", skip_all)]
+    pub fn delete_entry_if_match(
+        &mut self,
+        ident: &Identity,
+        id: &str,
+        filter: Filter<FilterInvalid>,
+        if_match: &str,
+    ) -> Result<(), OperationError> {
+        let current = self
+            .apply_search_id(ident, id, &filter)?
+            .ok_or(OperationError::HT0005EntryVersionMismatch)?;
+
+        let target_uuid = current.get_uuid();
+
+        if self.apply_version(target_uuid)? != if_match {
+            return Err(OperationError::HT0005EntryVersionMismatch);
+        }
+
+        let f_uuid = Filter::join_parts_and(
+            filter,
+            filter_all!(f_eq(Attribute::Uuid, PartialValue::Uuid(target_uuid))),
+        );
+        let de = DeleteEvent::from_parts(ident.clone(), &f_uuid, self)?;
+        self.delete(&de)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entry_version;
+    use crate::prelude::*;
+    use kanidm_proto::v1::Entry as ProtoEntry;
+
+    fn group_filter() -> Filter<FilterInvalid> {
+        filter_all!(f_eq(Attribute::Class, EntryClass::Group.into()))
+    }
+
+    fn proto_group(description: &str) -> ProtoEntry {
+        let mut entry = ProtoEntry::default();
+        entry
+            .attrs
+            .insert(ATTR_CLASS.to_string(), vec![ENTRYCLASS_GROUP.to_string()]);
+        entry
+            .attrs
+            .insert(ATTR_DESCRIPTION.to_string(), vec![description.to_string()]);
+        entry
+    }
+
+    #[qs_test]
+    async fn test_apply_entry_create_or_update(server: &QueryServer) {
+        let ident = Identity::from_internal();
+
+        // A dry run doesn't create the entry.
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        let response = server_txn
+            .apply_entry(
+                &ident,
+                "apply_group",
+                group_filter(),
+                &proto_group("first"),
+                None,
+                true,
+            )
+            .expect("Failed to plan entry");
+        assert!(response.created);
+        assert!(response.version.is_none());
+        drop(server_txn);
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn.name_to_uuid("apply_group").is_err());
+
+        let created = server_txn
+            .apply_entry(
+                &ident,
+                "apply_group",
+                group_filter(),
+                &proto_group("first"),
+                None,
+                false,
+            )
+            .expect("Failed to create entry");
+        assert!(created.created);
+        assert!(created
+            .modified
+            .iter()
+            .any(|attr| attr.as_str() == ATTR_DESCRIPTION));
+        assert!(server_txn.commit().is_ok());
+
+        // Applying the same description again changes nothing.
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        let unchanged = server_txn
+            .apply_entry(
+                &ident,
+                "apply_group",
+                group_filter(),
+                &proto_group("first"),
+                None,
+                false,
+            )
+            .expect("Failed to apply entry");
+        assert!(!unchanged.created);
+        assert!(unchanged.modified.is_empty());
+        assert_eq!(unchanged.version, created.version);
+
+        // A stale version is refused.
+        assert_eq!(
+            server_txn.apply_entry(
+                &ident,
+                "apply_group",
+                group_filter(),
+                &proto_group("second"),
+                Some("stale"),
+                false,
+            ),
+            Err(OperationError::HT0005EntryVersionMismatch)
+        );
+
+        let updated = server_txn
+            .apply_entry(
+                &ident,
+                "apply_group",
+                group_filter(),
+                &proto_group("second"),
+                created.version.as_deref(),
+                false,
+            )
+            .expect("Failed to update entry");
+        assert_eq!(updated.modified, vec![ATTR_DESCRIPTION.to_string()]);
+        assert_ne!(updated.version, created.version);
+
+        let entry = server_txn
+            .internal_search_uuid(updated.uuid)
+            .expect("Unable to find entry");
+        assert_eq!(entry_version(entry.as_ref()), updated.version);
+        assert_eq!(
+            entry.get_ava_single_utf8(Attribute::Description),
+            Some("second")
+        );
+
+        // A delete with a stale version is refused.
+        let stale = created.version.clone().expect("No version");
+        assert_eq!(
+            server_txn.delete_entry_if_match(&ident, "apply_group", group_filter(), &stale),
+            Err(OperationError::HT0005EntryVersionMismatch)
+        );
+
+        let current = updated.version.clone().expect("No version");
+        assert!(server_txn
+            .delete_entry_if_match(&ident, "apply_group", group_filter(), &current)
+            .is_ok());
+        assert!(server_txn.name_to_uuid("apply_group").is_err());
+
+        assert!(server_txn.commit().is_ok());
+    }
+}
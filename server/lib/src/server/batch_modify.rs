@@ -1,4 +1,5 @@
 use super::{ChangeFlag, QueryServerWriteTransaction};
+use crate::idm::audit::AuditEvent;
 use crate::prelude::*;
 use crate::server::Plugins;
 use std::collections::BTreeMap;
@@ -38,6 +39,8 @@ impl QueryServerWriteTransaction<'_> {
             security_info!(name = %me.ident, "batch modify initiator");
         }
 
+        self.check_maintenance_mode(&me.ident)?;
+
         // Validate input.
 
         // Is the modlist non zero?
@@ -215,6 +218,15 @@ impl QueryServerWriteTransaction<'_> {
             self.changed_flags.insert(ChangeFlag::APPLICATION)
         }
 
+        if !self.changed_flags.contains(ChangeFlag::ANNOUNCEMENT)
+            && norm_cand
+                .iter()
+                .chain(pre_candidates.iter().map(|e| e.as_ref()))
+                .any(|e| e.attribute_equality(Attribute::Class, &EntryClass::Announcement.into()))
+        {
+            self.changed_flags.insert(ChangeFlag::ANNOUNCEMENT)
+        }
+
         if !self.changed_flags.contains(ChangeFlag::OAUTH2)
             && norm_cand
                 .iter()
@@ -276,6 +288,21 @@ impl QueryServerWriteTransaction<'_> {
             changed = ?self.changed_flags.iter_names().collect::<Vec<_>>(),
         );
 
+        self.queue_activity(&me.ident, |source, actor, time| {
+            AuditEvent::EntriesModified {
+                source,
+                actor,
+                targets: norm_cand.iter().map(|e| e.get_uuid()).collect(),
+                attrs: me
+                    .modset
+                    .values()
+                    .flat_map(|modlist| modlist.iter())
+                    .map(|m| m.attr().clone())
+                    .collect(),
+                time,
+            }
+        });
+
         // return
         if me.ident.is_internal() {
             trace!("Modify operation success");
@@ -1,3 +1,4 @@
+use crate::idm::audit::AuditEvent;
 use crate::prelude::*;
 use crate::server::CreateEvent;
 use crate::server::{ChangeFlag, Plugins};
@@ -12,6 +13,8 @@ impl QueryServerWriteTransaction<'_> {
             security_info!(name = %ce.ident, "create initiator");
         }
 
+        self.check_maintenance_mode(&ce.ident)?;
+
         if ce.entries.is_empty() {
             request_error!("create: empty create request");
             return Err(OperationError::EmptyRequest);
@@ -121,6 +124,14 @@ impl QueryServerWriteTransaction<'_> {
             self.changed_flags.insert(ChangeFlag::APPLICATION)
         }
 
+        if !self.changed_flags.contains(ChangeFlag::ANNOUNCEMENT)
+            && commit_cand
+                .iter()
+                .any(|e| e.attribute_equality(Attribute::Class, &EntryClass::Announcement.into()))
+        {
+            self.changed_flags.insert(ChangeFlag::ANNOUNCEMENT)
+        }
+
         if !self.changed_flags.contains(ChangeFlag::OAUTH2)
             && commit_cand.iter().any(|e| {
                 e.attribute_equality(Attribute::Class, &EntryClass::OAuth2ResourceServer.into())
@@ -167,6 +178,15 @@ impl QueryServerWriteTransaction<'_> {
             changed = ?self.changed_flags.iter_names().collect::<Vec<_>>(),
         );
 
+        self.queue_activity(&ce.ident, |source, actor, time| {
+            AuditEvent::EntriesCreated {
+                source,
+                actor,
+                targets: commit_cand.iter().map(|e| e.get_uuid()).collect(),
+                time,
+            }
+        });
+
         // We are complete, finalise logging and return
 
         if ce.ident.is_internal() {
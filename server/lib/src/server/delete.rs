@@ -1,3 +1,4 @@
+use crate::idm::audit::AuditEvent;
 use crate::prelude::*;
 use crate::server::DeleteEvent;
 use crate::server::{ChangeFlag, Plugins};
@@ -16,6 +17,8 @@ impl QueryServerWriteTransaction<'_> {
             security_info!(name = %de.ident, "delete initiator");
         }
 
+        self.check_maintenance_mode(&de.ident)?;
+
         // Now, delete only what you can see
         let pre_candidates = self
             .impersonate_search_valid(de.filter.clone(), de.filter_orig.clone(), &de.ident)
@@ -125,6 +128,14 @@ impl QueryServerWriteTransaction<'_> {
             self.changed_flags.insert(ChangeFlag::APPLICATION)
         }
 
+        if !self.changed_flags.contains(ChangeFlag::ANNOUNCEMENT)
+            && del_cand
+                .iter()
+                .any(|e| e.attribute_equality(Attribute::Class, &EntryClass::Announcement.into()))
+        {
+            self.changed_flags.insert(ChangeFlag::ANNOUNCEMENT)
+        }
+
         if !self.changed_flags.contains(ChangeFlag::OAUTH2)
             && del_cand.iter().any(|e| {
                 e.attribute_equality(Attribute::Class, &EntryClass::OAuth2ResourceServer.into())
@@ -169,6 +180,15 @@ impl QueryServerWriteTransaction<'_> {
             changed = ?self.changed_flags.iter_names().collect::<Vec<_>>(),
         );
 
+        self.queue_activity(&de.ident, |source, actor, time| {
+            AuditEvent::EntriesDeleted {
+                source,
+                actor,
+                targets: del_cand.iter().map(|e| e.get_uuid()).collect(),
+                time,
+            }
+        });
+
         // Send result
         if de.ident.is_internal() {
             trace!("Delete operation success");
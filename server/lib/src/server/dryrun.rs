@@ -0,0 +1,149 @@
+//! Writes that are checked in full by plugins, schema and access controls, but that are never
+//! committed. These return the entries as they would be after the write, so that risky changes
+//! can be reviewed before they are made. The caller must drop the transaction rather than
+//! commit it.
+
+use super::QueryServerWriteTransaction;
+use crate::prelude::*;
+use kanidm_proto::v1::Entry as ProtoEntry;
+
+impl QueryServerWriteTransaction<'_> {
+    /// The entries as the identity is able to see them.
+    fn dry_run_entries(
+        &mut self,
+        ident: &Identity,
+        uuids: &[Uuid],
+    ) -> Result<Vec<ProtoEntry>, OperationError> {
+        if uuids.is_empty() {
+            return Ok(Vec::with_capacity(0));
+        }
+
+        let f_uuids = filter_all!(f_or(
+            uuids
+                .iter()
+                .map(|u| f_eq(Attribute::Uuid, PartialValue::Uuid(*u)))
+                .collect()
+        ));
+
+        self.impersonate_search_ext(f_uuids.clone(), f_uuids, ident)?
+            .iter()
+            .map(|entry| entry.to_pe(self))
+            .collect()
+    }
+
+    /// Create the entries, returning them as they would be once created.
+    #[instrument(level = "debug", skip_all)]
+    pub fn create_dry_run(
+        &mut self,
+        mut ce: CreateEvent,
+    ) -> Result<Vec<ProtoEntry>, OperationError> {
+        // Entries are normally given a uuid by the base plugin. Assign them here instead so
+        // that they can be found once created.
+        let uuids: Vec<Uuid> = ce
+            .entries
+            .iter_mut()
+            .map(|entry| match entry.get_ava_single_uuid(Attribute::Uuid) {
+                Some(uuid) => uuid,
+                None => {
+                    let uuid = Uuid::new_v4();
+                    entry.add_ava(Attribute::Uuid, Value::Uuid(uuid));
+                    uuid
+                }
+            })
+            .collect();
+
+        self.create(&ce)?;
+        self.dry_run_entries(&ce.ident, &uuids)
+    }
+
+    /// Modify the entries, returning them as they would be after the modification.
+    #[instrument(level = "debug", skip_all)]
+    pub fn modify_dry_run(&mut self, me: &ModifyEvent) -> Result<Vec<ProtoEntry>, OperationError> {
+        // The modification may change the entries so that they no longer match the filter.
+        let uuids: Vec<Uuid> = self
+            .impersonate_search_valid(me.filter.clone(), me.filter_orig.clone(), &me.ident)?
+            .iter()
+            .map(|entry| entry.get_uuid())
+            .collect();
+
+        self.modify(me)?;
+        self.dry_run_entries(&me.ident, &uuids)
+    }
+
+    /// Delete the entries, returning them as they were before they would be deleted.
+    #[instrument(level = "debug", skip_all)]
+    pub fn delete_dry_run(&mut self, de: &DeleteEvent) -> Result<Vec<ProtoEntry>, OperationError> {
+        let entries = self
+            .impersonate_search_ext_valid(de.filter.clone(), de.filter_orig.clone(), &de.ident)?
+            .iter()
+            .map(|entry| entry.to_pe(self))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.delete(de)?;
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[qs_test]
+    async fn test_dry_run_is_not_committed(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("dry_run_group"))
+        );
+
+        let entries = server_txn
+            .create_dry_run(CreateEvent::new_internal(vec![e1.clone()]))
+            .expect("Failed to check create");
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].attrs.contains_key(ATTR_UUID));
+        drop(server_txn);
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn.name_to_uuid("dry_run_group").is_err());
+
+        assert!(server_txn
+            .create(&CreateEvent::new_internal(vec![e1]))
+            .is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let f_name = filter!(f_eq(
+            Attribute::Name,
+            PartialValue::new_iname("dry_run_group")
+        ));
+
+        // The modified entry is returned even though it no longer matches the filter.
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        let me = ModifyEvent::new_internal_invalid(
+            f_name.clone(),
+            ModifyList::new_purge_and_set(Attribute::Name, Value::new_iname("dry_run_renamed")),
+        );
+        let entries = server_txn
+            .modify_dry_run(&me)
+            .expect("Failed to check modify");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].attrs.get(ATTR_NAME),
+            Some(&vec!["dry_run_renamed".to_string()])
+        );
+        drop(server_txn);
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        let de = DeleteEvent::new_internal_invalid(f_name);
+        let entries = server_txn
+            .delete_dry_run(&de)
+            .expect("Failed to check delete");
+        assert_eq!(entries.len(), 1);
+        drop(server_txn);
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn.name_to_uuid("dry_run_group").is_ok());
+        assert!(server_txn.name_to_uuid("dry_run_renamed").is_err());
+    }
+}
@@ -15,7 +15,7 @@ use kanidm_proto::internal::{ApiTokenPurpose, UatPurpose};
 use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
-use crate::value::Session;
+use crate::value::{ApiTokenPermission, Session};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Source {
@@ -116,6 +116,8 @@ pub struct Identity {
     pub(crate) session_id: Uuid,
     pub(crate) scope: AccessScope,
     limits: Limits,
+    white_pages_denied: bool,
+    api_token_permissions: Option<Arc<[ApiTokenPermission]>>,
 }
 
 impl std::fmt::Display for Identity {
@@ -152,10 +154,11 @@ impl Identity {
             session_id,
             scope,
             limits,
+            white_pages_denied: false,
+            api_token_permissions: None,
         }
     }
 
-    #[allow(dead_code)]
     pub(crate) fn source(&self) -> &Source {
         &self.source
     }
@@ -164,6 +167,25 @@ impl Identity {
         &self.limits
     }
 
+    /// Withhold the white pages from this identity, even if it is a member of
+    /// `idm_white_pages_readers`.
+    pub(crate) fn deny_white_pages(&mut self) {
+        self.white_pages_denied = true;
+    }
+
+    pub(crate) fn white_pages_denied(&self) -> bool {
+        self.white_pages_denied
+    }
+
+    /// Restrict this identity to only modify what the permissions of its api token allow.
+    pub(crate) fn restrict_to_api_token_permissions(&mut self, permissions: &[ApiTokenPermission]) {
+        self.api_token_permissions = Some(permissions.into());
+    }
+
+    pub(crate) fn api_token_permissions(&self) -> Option<&[ApiTokenPermission]> {
+        self.api_token_permissions.as_deref()
+    }
+
     #[cfg(test)]
     pub(crate) fn limits_mut(&mut self) -> &mut Limits {
         &mut self.limits
@@ -176,6 +198,8 @@ impl Identity {
             session_id: uuid!("00000000-0000-0000-0000-000000000000"),
             scope: AccessScope::ReadWrite,
             limits: Limits::unlimited(),
+            white_pages_denied: false,
+            api_token_permissions: None,
         }
     }
 
@@ -189,10 +213,12 @@ impl Identity {
             session_id: uuid!("00000000-0000-0000-0000-000000000000"),
             scope: AccessScope::ReadOnly,
             limits: Limits::unlimited(),
+            white_pages_denied: false,
+            api_token_permissions: None,
         }
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "test"))]
     pub fn from_impersonate_entry_readwrite(
         entry: Arc<Entry<EntrySealed, EntryCommitted>>,
     ) -> Self {
@@ -202,6 +228,90 @@ impl Identity {
             session_id: uuid!("00000000-0000-0000-0000-000000000000"),
             scope: AccessScope::ReadWrite,
             limits: Limits::unlimited(),
+            white_pages_denied: false,
+            api_token_permissions: None,
+        }
+    }
+
+    /// The identity of an account as if it had a read write session. This is used to preview
+    /// how a change to the access controls would affect the account.
+    pub(crate) fn from_access_preview(entry: Arc<Entry<EntrySealed, EntryCommitted>>) -> Self {
+        Identity {
+            origin: IdentType::User(IdentUser { entry }),
+            source: Source::Internal,
+            session_id: uuid!("00000000-0000-0000-0000-000000000000"),
+            scope: AccessScope::ReadWrite,
+            limits: Limits::unlimited(),
+            white_pages_denied: false,
+            api_token_permissions: None,
+        }
+    }
+
+    /// The identity of the admin account, authenticated by the break glass key.
+    pub(crate) fn from_break_glass(
+        entry: Arc<Entry<EntrySealed, EntryCommitted>>,
+        source: Source,
+        session_id: Uuid,
+    ) -> Self {
+        Identity {
+            origin: IdentType::User(IdentUser { entry }),
+            source,
+            session_id,
+            scope: AccessScope::ReadWrite,
+            limits: Limits::unlimited(),
+            white_pages_denied: false,
+            api_token_permissions: None,
+        }
+    }
+
+    /// The identity of a person who has proven control of their account through account
+    /// recovery. This has the access the person would have with a privileged session.
+    pub(crate) fn from_account_recovery(
+        entry: Arc<Entry<EntrySealed, EntryCommitted>>,
+        source: Source,
+    ) -> Self {
+        Identity {
+            origin: IdentType::User(IdentUser { entry }),
+            source,
+            session_id: Uuid::new_v4(),
+            scope: AccessScope::ReadWrite,
+            limits: Limits::default(),
+            white_pages_denied: false,
+            api_token_permissions: None,
+        }
+    }
+
+    /// The identity of a person who authenticated with an expired password, and must change it
+    /// before a session is issued.
+    pub(crate) fn from_password_expired(
+        entry: Arc<Entry<EntrySealed, EntryCommitted>>,
+        source: Source,
+    ) -> Self {
+        Identity {
+            origin: IdentType::User(IdentUser { entry }),
+            source,
+            session_id: Uuid::new_v4(),
+            scope: AccessScope::ReadWrite,
+            limits: Limits::default(),
+            white_pages_denied: false,
+            api_token_permissions: None,
+        }
+    }
+
+    /// The identity of a person who has re-entered their password to change it over LDAP.
+    /// LDAP sessions are otherwise read only, so this is only used for the password change.
+    pub(crate) fn from_ldap_password_modify(
+        entry: Arc<Entry<EntrySealed, EntryCommitted>>,
+        source: Source,
+    ) -> Self {
+        Identity {
+            origin: IdentType::User(IdentUser { entry }),
+            source,
+            session_id: Uuid::new_v4(),
+            scope: AccessScope::ReadWrite,
+            limits: Limits::default(),
+            white_pages_denied: false,
+            api_token_permissions: None,
         }
     }
 
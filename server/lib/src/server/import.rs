@@ -0,0 +1,220 @@
+//! Bulk import of entries. An import is applied in two phases so that the entries it contains
+//! can be in any order. First every entry is created without the attributes that refer to
+//! other entries, and then those references are added once the entries they refer to exist.
+
+use super::QueryServerWriteTransaction;
+use crate::prelude::*;
+use crate::server::batch_modify::{BatchModifyEvent, ModSetValid};
+use kanidm_proto::internal::{Modify as ProtoModify, ModifyList as ProtoModifyList};
+use kanidm_proto::v1::Entry as ProtoEntry;
+use std::collections::BTreeMap;
+
+impl QueryServerWriteTransaction<'_> {
+    fn import_attribute_syntax(&self, attr: &str) -> Option<SyntaxType> {
+        self.get_schema()
+            .get_attributes()
+            .get(&Attribute::from(attr))
+            .map(|schema_a| schema_a.syntax)
+    }
+
+    fn is_reference_attribute(&self, attr: &str) -> bool {
+        self.import_attribute_syntax(attr) == Some(SyntaxType::ReferenceUuid)
+    }
+
+    /// Create the entries of an import, without the attributes that refer to other entries.
+    /// Ssh public keys may be prefixed with their tag as `tag: key`, as they are in a search
+    /// result. Otherwise they are tagged in the order they are imported, as keys from other
+    /// systems don't have a tag.
+    #[instrument(level = "debug", skip_all)]
+    pub fn import_entries(
+        &mut self,
+        ident: &Identity,
+        entries: &[ProtoEntry],
+    ) -> Result<(), OperationError> {
+        let mut candidates = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            let mut attrs = BTreeMap::new();
+            let mut ssh_keys = Vec::new();
+
+            for (attr, values) in entry.attrs.iter() {
+                match self.import_attribute_syntax(attr) {
+                    Some(SyntaxType::ReferenceUuid) => {}
+                    Some(SyntaxType::SshKey) => ssh_keys.extend(
+                        values
+                            .iter()
+                            .map(|key| (Attribute::from(attr.as_str()), key)),
+                    ),
+                    _ => {
+                        attrs.insert(attr.clone(), values.clone());
+                    }
+                }
+            }
+
+            let mut candidate = Entry::from_proto_entry(&ProtoEntry { attrs }, self)?;
+
+            for (index, (attr, key)) in ssh_keys.into_iter().enumerate() {
+                let value = match key.split_once(": ") {
+                    Some((tag, key)) if !tag.contains(' ') => Value::new_sshkey_str(tag, key)?,
+                    _ => Value::new_sshkey_str(&format!("imported_{}", index), key)?,
+                };
+                candidate.add_ava(attr, value);
+            }
+
+            candidates.push(candidate);
+        }
+
+        let ce = CreateEvent {
+            ident: ident.clone(),
+            entries: candidates,
+        };
+
+        self.create(&ce)
+    }
+
+    /// Add the attributes that refer to other entries to the entries of an import. This
+    /// fails if the entry, or any entry that it refers to, does not exist.
+    #[instrument(level = "debug", skip_all)]
+    pub fn import_references(
+        &mut self,
+        ident: &Identity,
+        entries: &[ProtoEntry],
+    ) -> Result<(), OperationError> {
+        let mut proto_modset: BTreeMap<Uuid, Vec<ProtoModify>> = BTreeMap::new();
+
+        for entry in entries {
+            let mods: Vec<_> = entry
+                .attrs
+                .iter()
+                .filter(|(attr, _)| self.is_reference_attribute(attr))
+                .flat_map(|(attr, values)| {
+                    values
+                        .iter()
+                        .map(|value| ProtoModify::Present(attr.clone(), value.clone()))
+                })
+                .collect();
+
+            if mods.is_empty() {
+                continue;
+            }
+
+            let target = entry
+                .attrs
+                .get(ATTR_UUID)
+                .or_else(|| entry.attrs.get(ATTR_NAME))
+                .and_then(|values| values.first())
+                .ok_or_else(|| {
+                    OperationError::InvalidAttribute(
+                        "An imported entry must have a uuid or name".to_string(),
+                    )
+                })?;
+
+            let target_uuid = self.name_to_uuid(target)?;
+
+            proto_modset.entry(target_uuid).or_default().extend(mods);
+        }
+
+        if proto_modset.is_empty() {
+            return Ok(());
+        }
+
+        let mut modset = ModSetValid::new();
+
+        for (target_uuid, mods) in proto_modset {
+            let modlist = ModifyList::from(&ProtoModifyList::new_list(mods), self)?
+                .validate(self.get_schema())
+                .map_err(OperationError::SchemaViolation)?;
+
+            modset.insert(target_uuid, modlist);
+        }
+
+        let me = BatchModifyEvent {
+            ident: ident.clone(),
+            modset,
+        };
+
+        self.batch_modify(&me)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use kanidm_proto::v1::Entry as ProtoEntry;
+
+    fn proto_entry(attrs: &[(&str, &str)]) -> ProtoEntry {
+        let mut entry = ProtoEntry::default();
+        for (attr, value) in attrs {
+            entry
+                .attrs
+                .entry(attr.to_string())
+                .or_default()
+                .push(value.to_string());
+        }
+        entry
+    }
+
+    #[qs_test]
+    async fn test_import_entries_and_references(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        let ident = Identity::from_internal();
+
+        // The group refers to a person that is later in the import.
+        let entries = vec![
+            proto_entry(&[
+                (ATTR_CLASS, ENTRYCLASS_GROUP),
+                (ATTR_NAME, "import_group"),
+                (ATTR_MEMBER, "import_person"),
+            ]),
+            proto_entry(&[
+                (ATTR_CLASS, ENTRYCLASS_PERSON),
+                (ATTR_CLASS, ENTRYCLASS_ACCOUNT),
+                (ATTR_NAME, "import_person"),
+                (ATTR_DISPLAYNAME, "Import Person"),
+                (
+                    ATTR_SSH_PUBLICKEY,
+                    "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAeGW1P6Pc2rPq0XqbRaDKBcXZUPRklo0L1EyR30CwoP",
+                ),
+            ]),
+        ];
+
+        server_txn
+            .import_entries(&ident, &entries)
+            .expect("Failed to import entries");
+
+        let group_uuid = server_txn
+            .name_to_uuid("import_group")
+            .expect("Group was not created");
+        let group = server_txn
+            .internal_search_uuid(group_uuid)
+            .expect("Group was not created");
+        assert!(!group.attribute_pres(Attribute::Member));
+
+        server_txn
+            .import_references(&ident, &entries)
+            .expect("Failed to import references");
+
+        let person_uuid = server_txn
+            .name_to_uuid("import_person")
+            .expect("Person was not created");
+        let person = server_txn
+            .internal_search_uuid(person_uuid)
+            .expect("Person was not created");
+        assert!(person.attribute_equality(
+            Attribute::SshPublicKey,
+            &PartialValue::new_sshkey_tag_s("imported_0")
+        ));
+        let group = server_txn
+            .internal_search_uuid(group_uuid)
+            .expect("Group was not created");
+        assert!(group.attribute_equality(Attribute::Member, &PartialValue::Refer(person_uuid)));
+
+        // A reference to an entry that doesn't exist fails.
+        let missing = vec![proto_entry(&[
+            (ATTR_NAME, "import_group"),
+            (ATTR_MEMBER, "import_missing"),
+        ])];
+
+        assert!(server_txn.import_references(&ident, &missing).is_err());
+    }
+}
@@ -9,12 +9,32 @@ use std::sync::Arc;
 
 use compact_jwt::compact::{JweAlg, JweCompact, JweEnc};
 use compact_jwt::crypto::{JweA128GCMEncipher, JweA128KWEncipher};
-use compact_jwt::jwe::Jwe;
+use compact_jwt::crypto::{JwsRs256Signer, JwsRs256Verifier};
+use compact_jwt::jwe::{Jwe, JweBuilder};
+use compact_jwt::jws::JwsBuilder;
 use compact_jwt::traits::*;
 use compact_jwt::{
     JwaAlg, Jwk, Jws, JwsCompact, JwsEs256Signer, JwsEs256Verifier, JwsSigner, JwsSignerToVerifier,
 };
 
+use kanidm_lib_crypto::kerberos::{
+    kerberos_decrypt, kerberos_derive_service_key, kerberos_encrypt, kerberos_generate_key,
+    kerberos_issue_ticket, KerberosCredential, KerberosPrincipal, KerberosServiceKey,
+    KRB5_AES256_KEY_LEN,
+};
+use kanidm_lib_crypto::mtls::{
+    build_replication_identity, build_replication_trust_anchor, get_group,
+};
+use kanidm_lib_crypto::prelude::{PKey, Private, X509};
+use kanidm_lib_crypto::saml2::{
+    build_saml2_signing_certificate, generate_saml2_signing_key, saml2_sign, saml2_verify,
+};
+use kanidm_lib_crypto::ssh_ca::{
+    build_ssh_user_certificate, is_ssh_certificate_issued_by, ssh_ca_public_key,
+};
+use openssl::ec::EcKey;
+use openssl::sha;
+
 use std::ops::Bound::{Included, Unbounded};
 
 use crate::value::{KeyStatus, KeyUsage};
@@ -61,6 +81,10 @@ impl KeyProviderInternal {
             uuid,
             jws_es256: None,
             jwe_a128gcm: None,
+            repl_trust_anchor: None,
+            ssh_ca: None,
+            saml2_signing: None,
+            kerberos: None,
         }))
     }
 
@@ -73,7 +97,12 @@ impl KeyProviderInternal {
         debug!(?uuid, "Loading key object ...");
 
         let mut jws_es256: Option<KeyObjectInternalJwtEs256> = None;
+        let mut jws_rs256: Option<KeyObjectInternalJwtRs256> = None;
         let mut jwe_a128gcm: Option<KeyObjectInternalJweA128GCM> = None;
+        let mut repl_trust_anchor: Option<KeyObjectInternalReplTrustAnchor> = None;
+        let mut ssh_ca: Option<KeyObjectInternalSshCa> = None;
+        let mut saml2_signing: Option<KeyObjectInternalSaml2Signing> = None;
+        let mut kerberos: Option<KeyObjectInternalKerberos> = None;
 
         if let Some(key_internal_map) = entry
             .get_ava_set(Attribute::KeyInternalData)
@@ -104,6 +133,18 @@ impl KeyProviderInternal {
                             *valid_from,
                         )?;
                     }
+                    KeyUsage::JwsRs256 => {
+                        let jws_rs256_ref =
+                            jws_rs256.get_or_insert_with(KeyObjectInternalJwtRs256::default);
+
+                        jws_rs256_ref.load(
+                            key_id,
+                            *status,
+                            status_cid.clone(),
+                            der,
+                            *valid_from,
+                        )?;
+                    }
                     KeyUsage::JweA128GCM => {
                         let jwe_a128gcm_ref =
                             jwe_a128gcm.get_or_insert_with(KeyObjectInternalJweA128GCM::default);
@@ -116,6 +157,41 @@ impl KeyProviderInternal {
                             *valid_from,
                         )?;
                     }
+                    KeyUsage::ReplTrustAnchor => {
+                        let repl_trust_anchor_ref = repl_trust_anchor
+                            .get_or_insert_with(KeyObjectInternalReplTrustAnchor::default);
+
+                        repl_trust_anchor_ref.load(
+                            key_id,
+                            *status,
+                            status_cid.clone(),
+                            der,
+                            *valid_from,
+                        )?;
+                    }
+                    KeyUsage::SshCa => {
+                        let ssh_ca_ref = ssh_ca.get_or_insert_with(KeyObjectInternalSshCa::default);
+
+                        ssh_ca_ref.load(key_id, *status, status_cid.clone(), der, *valid_from)?;
+                    }
+                    KeyUsage::Saml2Signing => {
+                        let saml2_signing_ref = saml2_signing
+                            .get_or_insert_with(KeyObjectInternalSaml2Signing::default);
+
+                        saml2_signing_ref.load(
+                            key_id,
+                            *status,
+                            status_cid.clone(),
+                            der,
+                            *valid_from,
+                        )?;
+                    }
+                    KeyUsage::Kerberos => {
+                        let kerberos_ref =
+                            kerberos.get_or_insert_with(KeyObjectInternalKerberos::default);
+
+                        kerberos_ref.load(key_id, *status, status_cid.clone(), der, *valid_from)?;
+                    }
                 }
             }
         }
@@ -124,7 +200,12 @@ impl KeyProviderInternal {
             provider,
             uuid,
             jws_es256,
+            jws_rs256,
             jwe_a128gcm,
+            repl_trust_anchor,
+            ssh_ca,
+            saml2_signing,
+            kerberos,
         })))
     }
 
@@ -713,6 +794,13 @@ impl KeyObjectInternalJwtEs256 {
         }
     }
 
+    fn public_jwks(&self) -> Result<Vec<Jwk>, OperationError> {
+        self.all
+            .keys()
+            .filter_map(|kid| self.public_jwk(kid).transpose())
+            .collect()
+    }
+
     #[cfg(test)]
     fn kid_status(&self, key_id: &KeyId) -> Result<Option<KeyStatus>, OperationError> {
         if let Some(key_to_check) = self.all.get(key_id) {
@@ -729,166 +817,1652 @@ impl KeyObjectInternalJwtEs256 {
 }
 
 #[derive(Clone)]
-pub struct KeyObjectInternal {
-    provider: Arc<KeyProviderInternal>,
-    uuid: Uuid,
-    jws_es256: Option<KeyObjectInternalJwtEs256>,
-    jwe_a128gcm: Option<KeyObjectInternalJweA128GCM>,
-    // If you add more types here you need to add these to rotate
-    // and revoke.
+enum InternalJwtRs256Status {
+    Valid {
+        verifier: JwsRs256Verifier,
+        public_jwk: Jwk,
+        private_der: Vec<u8>,
+    },
+    Retained {
+        verifier: JwsRs256Verifier,
+        public_jwk: Jwk,
+        public_der: Vec<u8>,
+    },
+    Revoked {
+        public_jwk: Jwk,
+        public_der: Vec<u8>,
+    },
 }
 
-#[cfg(test)]
-impl KeyObjectInternal {
-    pub fn new_test() -> Arc<KeyObject> {
-        let provider = Arc::new(KeyProviderInternal::create_test_provider());
-
-        let mut key_object = provider
-            .create_new_key_object(Uuid::new_v4(), provider.clone())
-            .expect("Unable to build new key object");
+#[derive(Clone)]
+struct InternalJwtRs256 {
+    valid_from: u64,
+    status: InternalJwtRs256Status,
+    status_cid: Cid,
+}
 
-        key_object
-            .jws_es256_assert(Duration::from_secs(0), &Cid::new_zero())
-            .expect("Unable to add jws_es256 to key object");
+#[derive(Default, Clone)]
+struct KeyObjectInternalJwtRs256 {
+    // active signing keys are in a BTreeMap indexed by their valid_from
+    // time so that we can retrieve the active key.
+    active: BTreeMap<u64, JwsRs256Signer>,
 
-        Arc::new(key_object)
-    }
+    // All keys are stored by their KeyId for fast lookup. Keys internally have a
+    // current status which is checked for signature validation.
+    all: BTreeMap<KeyId, InternalJwtRs256>,
 }
 
-impl KeyObjectT for KeyObjectInternal {
-    fn uuid(&self) -> Uuid {
-        self.uuid
-    }
-
-    fn duplicate(&self) -> KeyObject {
-        Box::new(self.clone())
+impl KeyObjectInternalJwtRs256 {
+    // Rsa public keys are persisted as their JWK rather than DER, since that is the form
+    // that we need to load a verifier from.
+    fn public_jwk_to_der(public_jwk: &Jwk) -> Result<Vec<u8>, OperationError> {
+        serde_json::to_vec(public_jwk).map_err(|err| {
+            error!(?err, "Unable to serialise rs256 public jwk");
+            OperationError::KP0069KeyObjectJwsRs256PublicJwkInvalid
+        })
     }
 
-    fn rotate_keys(&mut self, rotation_time: Duration, cid: &Cid) -> Result<(), OperationError> {
-        if let Some(jws_es256_object) = &mut self.jws_es256 {
-            jws_es256_object.new_active(rotation_time, cid)?;
-        }
+    fn public_jwk_from_der(der: &[u8]) -> Result<(Jwk, JwsRs256Verifier), OperationError> {
+        let public_jwk: Jwk = serde_json::from_slice(der).map_err(|err| {
+            error!(?err, "Unable to deserialise rs256 public jwk");
+            OperationError::KP0069KeyObjectJwsRs256PublicJwkInvalid
+        })?;
 
-        if let Some(jwe_a128_gcm) = &mut self.jwe_a128gcm {
-            jwe_a128_gcm.new_active(rotation_time, cid)?;
-        }
+        let verifier = JwsRs256Verifier::try_from(&public_jwk).map_err(|err| {
+            error!(?err, "Unable to load rs256 verifier from public jwk");
+            OperationError::KP0069KeyObjectJwsRs256PublicJwkInvalid
+        })?;
 
-        Ok(())
+        Ok((public_jwk, verifier))
     }
 
-    fn revoke_keys(
-        &mut self,
-        revoke_set: &BTreeSet<String>,
-        cid: &Cid,
-    ) -> Result<(), OperationError> {
-        for revoke_key_id in revoke_set.iter() {
-            let mut has_revoked = false;
+    fn signer_parts(signer: &JwsRs256Signer) -> Result<(JwsRs256Verifier, Jwk), OperationError> {
+        let verifier = signer.get_verifier().map_err(|jwt_error| {
+            error!(
+                ?jwt_error,
+                "Unable to produce jwt rs256 verifier from signer"
+            );
+            OperationError::KP0010KeyObjectSignerToVerifier
+        })?;
 
-            if let Some(jws_es256_object) = &mut self.jws_es256 {
-                if jws_es256_object.revoke(revoke_key_id, cid)? {
-                    has_revoked = true;
-                }
-            };
+        let public_jwk = signer.public_key_as_jwk().map_err(|jwt_error| {
+            error!(?jwt_error, "Unable to produce jwt rs256 public jwk");
+            OperationError::KP0069KeyObjectJwsRs256PublicJwkInvalid
+        })?;
 
-            if let Some(jwe_a128_gcm) = &mut self.jwe_a128gcm {
-                if jwe_a128_gcm.revoke(revoke_key_id, cid)? {
-                    has_revoked = true;
-                }
-            };
+        Ok((verifier, public_jwk))
+    }
 
-            if !has_revoked {
-                error!(?revoke_key_id, "Unable to revoked key, id not found");
-                return Err(OperationError::KP0026KeyObjectNoSuchKey);
-            }
-        }
+    fn get_valid_signer(&self, time: Duration) -> Option<&JwsRs256Signer> {
+        let ct_secs = time.as_secs();
 
-        Ok(())
+        self.active
+            .range((Unbounded, Included(ct_secs)))
+            .next_back()
+            .map(|(_time, signer)| signer)
     }
 
-    fn jws_es256_sign(
-        &self,
-        jws: &Jws,
-        current_time: Duration,
-    ) -> Result<JwsCompact, OperationError> {
-        if let Some(jws_es256_object) = &self.jws_es256 {
-            jws_es256_object.sign(jws, current_time)
+    fn assert_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        if self.get_valid_signer(valid_from).is_none() {
+            // This means there is no active signing key, so we need to create one.
+            warn!("no active jwt rs256 found, creating a new one ...");
+            self.new_active(valid_from, cid)
         } else {
-            error!(provider_uuid = ?self.uuid, "jwt es256 not available on this provider");
-            Err(OperationError::KP0017KeyProviderNoSuchKey)
+            Ok(())
         }
     }
 
-    fn jws_verify(&self, jwsc: &JwsCompact) -> Result<Jws, OperationError> {
-        // Seems weird, but we can match on alg to select the provider.
-        let alg = jwsc.alg();
+    fn new_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        let valid_from = valid_from.as_secs();
 
-        match alg {
-            JwaAlg::ES256 => {
-                if let Some(jws_es256_object) = &self.jws_es256 {
-                    jws_es256_object.verify(jwsc)
-                } else {
-                    error!(provider_uuid = ?self.uuid, "jwt es256 not available on this provider");
-                    Err(OperationError::KP0018KeyProviderNoSuchKey)
-                }
-            }
-            unsupported_alg => {
-                // unsupported rn.
-                error!(provider_uuid = ?self.uuid, ?unsupported_alg, "algorithm not available on this provider");
-                Err(OperationError::KP0019KeyProviderUnsupportedAlgorithm)
-            }
-        }
+        let signer = JwsRs256Signer::generate_legacy_rs256().map_err(|jwt_error| {
+            error!(?jwt_error, "Unable to generate new jwt rs256 signing key");
+            OperationError::KP0066KeyObjectJwsRs256Generation
+        })?;
+
+        let (verifier, public_jwk) = Self::signer_parts(&signer)?;
+
+        let private_der = signer.private_key_to_der().map_err(|jwt_error| {
+            error!(?jwt_error, "Unable to convert signing key to DER");
+            OperationError::KP0009KeyObjectPrivateToDer
+        })?;
+
+        self.active.insert(valid_from, signer.clone());
+
+        let kid = signer.get_kid().to_string();
+
+        self.all.insert(
+            kid,
+            InternalJwtRs256 {
+                valid_from,
+                status: InternalJwtRs256Status::Valid {
+                    verifier,
+                    public_jwk,
+                    private_der,
+                },
+                status_cid: cid.clone(),
+            },
+        );
+
+        Ok(())
     }
 
-    fn jws_public_jwk(&self, kid: &str) -> Result<Option<Jwk>, OperationError> {
-        if let Some(jws_es256_object) = &self.jws_es256 {
-            jws_es256_object.public_jwk(kid)
+    fn revoke(&mut self, revoke_key_id: &KeyId, cid: &Cid) -> Result<bool, OperationError> {
+        if let Some(key_to_revoke) = self.all.get_mut(revoke_key_id) {
+            let public_jwk = match &key_to_revoke.status {
+                InternalJwtRs256Status::Valid { public_jwk, .. }
+                | InternalJwtRs256Status::Retained { public_jwk, .. }
+                | InternalJwtRs256Status::Revoked { public_jwk, .. } => public_jwk,
+            }
+            .clone();
+
+            let public_der = Self::public_jwk_to_der(&public_jwk)?;
+
+            key_to_revoke.status = InternalJwtRs256Status::Revoked {
+                public_jwk,
+                public_der,
+            };
+            key_to_revoke.status_cid = cid.clone();
+
+            let valid_from = key_to_revoke.valid_from;
+
+            // Remove it from the active set.
+            self.active.remove(&valid_from);
+
+            Ok(true)
         } else {
-            Ok(None)
+            // We didn't revoke anything
+            Ok(false)
         }
     }
 
-    fn jws_es256_import(
+    fn load(
         &mut self,
-        import_keys: &SmolSet<[Vec<u8>; 1]>,
-        valid_from: Duration,
-        cid: &Cid,
+        id: &str,
+        status: KeyStatus,
+        status_cid: Cid,
+        der: &[u8],
+        valid_from: u64,
     ) -> Result<(), OperationError> {
-        let koi = self
-            .jws_es256
-            .get_or_insert_with(KeyObjectInternalJwtEs256::default);
-
-        koi.import(import_keys, valid_from, cid)
-    }
+        let id: KeyId = id.to_string();
 
-    fn jws_es256_assert(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
-        let koi = self
-            .jws_es256
-            .get_or_insert_with(KeyObjectInternalJwtEs256::default);
+        let status = match status {
+            KeyStatus::Valid => {
+                let signer = JwsRs256Signer::from_rs256_der(der).map_err(|err| {
+                    error!(?err, ?id, "Unable to load rs256 DER signer");
+                    OperationError::KP0067KeyObjectJwsRs256DerInvalid
+                })?;
 
-        koi.assert_active(valid_from, cid)
-    }
+                let (verifier, public_jwk) = Self::signer_parts(&signer)?;
 
-    fn jwe_decrypt(&self, jwec: &JweCompact) -> Result<Jwe, OperationError> {
-        let (alg, enc) = jwec.get_alg_enc();
+                self.active.insert(valid_from, signer);
 
-        match (alg, enc) {
-            (JweAlg::A128KW, JweEnc::A128GCM) => {
-                if let Some(jwe_a128_gcm) = &self.jwe_a128gcm {
-                    jwe_a128_gcm.decipher(jwec)
-                } else {
-                    error!(provider_uuid = ?self.uuid, "jwe a128gcm not available on this provider");
-                    Err(OperationError::KP0033KeyProviderNoSuchKey)
+                InternalJwtRs256Status::Valid {
+                    verifier,
+                    public_jwk,
+                    private_der: der.to_vec(),
                 }
             }
-            (unsupported_alg, unsupported_enc) => {
-                // unsupported rn.
-                error!(provider_uuid = ?self.uuid, ?unsupported_alg, ?unsupported_enc, "algorithm+encryption not available on this provider");
-                Err(OperationError::KP0019KeyProviderUnsupportedAlgorithm)
+            KeyStatus::Retained => {
+                let (public_jwk, verifier) = Self::public_jwk_from_der(der)?;
+
+                InternalJwtRs256Status::Retained {
+                    verifier,
+                    public_jwk,
+                    public_der: der.to_vec(),
+                }
             }
-        }
-    }
+            KeyStatus::Revoked => {
+                let (public_jwk, _untrusted_verifier) = Self::public_jwk_from_der(der)?;
 
-    fn jwe_a128gcm_assert(
+                InternalJwtRs256Status::Revoked {
+                    public_jwk,
+                    public_der: der.to_vec(),
+                }
+            }
+        };
+
+        self.all.insert(
+            id,
+            InternalJwtRs256 {
+                valid_from,
+                status,
+                status_cid,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn to_key_iter(&self) -> impl Iterator<Item = (KeyId, KeyInternalData)> + '_ {
+        self.all.iter().map(|(key_id, internal_jwt)| {
+            let usage = KeyUsage::JwsRs256;
+
+            let valid_from = internal_jwt.valid_from;
+            let status_cid = internal_jwt.status_cid.clone();
+
+            let (status, der) = match &internal_jwt.status {
+                InternalJwtRs256Status::Valid { private_der, .. } => {
+                    (KeyStatus::Valid, private_der.clone())
+                }
+                InternalJwtRs256Status::Retained { public_der, .. } => {
+                    (KeyStatus::Retained, public_der.clone())
+                }
+                InternalJwtRs256Status::Revoked { public_der, .. } => {
+                    (KeyStatus::Revoked, public_der.clone())
+                }
+            };
+
+            (
+                key_id.clone(),
+                KeyInternalData {
+                    usage,
+                    valid_from,
+                    der,
+                    status,
+                    status_cid,
+                },
+            )
+        })
+    }
+
+    fn sign<V: JwsSignable>(
+        &self,
+        jws: &V,
+        current_time: Duration,
+    ) -> Result<V::Signed, OperationError> {
+        let Some(signing_key) = self.get_valid_signer(current_time) else {
+            error!("No signing keys available. This may indicate that no keys are valid yet!");
+            return Err(OperationError::KP0020KeyObjectNoActiveSigningKeys);
+        };
+
+        signing_key.sign(jws).map_err(|jwt_err| {
+            error!(?jwt_err, "Unable to sign jws");
+            OperationError::KP0068KeyObjectJwsRs256Signature
+        })
+    }
+
+    fn verify<V: JwsVerifiable>(&self, jwsc: &V) -> Result<V::Verified, OperationError> {
+        let internal_jws = jwsc
+            .kid()
+            .and_then(|kid| {
+                debug!(?kid);
+                self.all.get(kid)
+            })
+            .ok_or_else(|| {
+                error!("JWS is signed by a key that is not present in this KeyObject");
+                OperationError::KP0022KeyObjectJwsNotAssociated
+            })?;
+
+        match &internal_jws.status {
+            InternalJwtRs256Status::Valid { verifier, .. }
+            | InternalJwtRs256Status::Retained { verifier, .. } => {
+                verifier.verify(jwsc).map_err(|jwt_err| {
+                    error!(?jwt_err, "Failed to verify jws");
+                    OperationError::KP0024KeyObjectJwsInvalid
+                })
+            }
+            InternalJwtRs256Status::Revoked { .. } => {
+                error!("The key used to sign this JWS has been revoked.");
+                Err(OperationError::KP0023KeyObjectJwsKeyRevoked)
+            }
+        }
+    }
+
+    fn public_jwk(&self, key_id: &str) -> Option<Jwk> {
+        self.all
+            .get(key_id)
+            .and_then(|key_to_check| match &key_to_check.status {
+                InternalJwtRs256Status::Valid { public_jwk, .. }
+                | InternalJwtRs256Status::Retained { public_jwk, .. } => Some(public_jwk.clone()),
+                InternalJwtRs256Status::Revoked { .. } => None,
+            })
+    }
+
+    fn public_jwks(&self) -> Vec<Jwk> {
+        self.all
+            .keys()
+            .filter_map(|kid| self.public_jwk(kid))
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+enum InternalReplTrustAnchorStatus {
+    Valid {
+        ca_key: PKey<Private>,
+        ca_cert: X509,
+        private_der: Vec<u8>,
+    },
+    Retained {
+        ca_cert: X509,
+        private_der: Vec<u8>,
+    },
+    Revoked,
+}
+
+#[derive(Clone)]
+struct InternalReplTrustAnchor {
+    valid_from: u64,
+    status: InternalReplTrustAnchorStatus,
+    status_cid: Cid,
+}
+
+/// The trust anchors that replication partners of this domain use to verify each other. Each
+/// server issues its own replication identity from the active anchor.
+#[derive(Default, Clone)]
+struct KeyObjectInternalReplTrustAnchor {
+    // active anchors are in a BTreeMap indexed by their valid_from time so that we
+    // can retrieve the anchor that issues new identities.
+    active: BTreeMap<u64, (PKey<Private>, X509)>,
+
+    // All anchors are stored by their KeyId. Valid and retained anchors are trusted.
+    all: BTreeMap<KeyId, InternalReplTrustAnchor>,
+}
+
+impl KeyObjectInternalReplTrustAnchor {
+    /// Build the certificate of an anchor from its key. The key id is derived from the
+    /// public key, so that every server derives the same id for the same anchor.
+    fn build_anchor(
+        ca_key: &PKey<Private>,
+        valid_from: u64,
+    ) -> Result<(KeyId, X509), OperationError> {
+        let public_der = ca_key.public_key_to_der().map_err(|err| {
+            error!(?err, "Unable to convert trust anchor public key to DER");
+            OperationError::KP0048KeyObjectReplTrustAnchorInvalid
+        })?;
+
+        let kid = hex::encode(sha::sha256(&public_der));
+
+        let ca_cert =
+            build_replication_trust_anchor(ca_key, &kid, valid_from as i64).map_err(|err| {
+                error!(?err, "Unable to build replication trust anchor certificate");
+                OperationError::KP0048KeyObjectReplTrustAnchorInvalid
+            })?;
+
+        Ok((kid, ca_cert))
+    }
+
+    fn get_valid_anchor(&self, time: Duration) -> Option<&(PKey<Private>, X509)> {
+        let ct_secs = time.as_secs();
+
+        self.active
+            .range((Unbounded, Included(ct_secs)))
+            .next_back()
+            .map(|(_time, anchor)| anchor)
+    }
+
+    fn assert_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        if self.get_valid_anchor(valid_from).is_none() {
+            // This means there is no active anchor, so we need to create one.
+            warn!("no active replication trust anchor found, creating a new one ...");
+            self.new_active(valid_from, cid)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn new_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        let valid_from = valid_from.as_secs();
+
+        let ca_key = get_group()
+            .and_then(|group| EcKey::generate(&group))
+            .and_then(PKey::from_ec_key)
+            .map_err(|err| {
+                error!(?err, "Unable to generate new replication trust anchor key");
+                OperationError::KP0047KeyObjectReplTrustAnchorGeneration
+            })?;
+
+        let private_der = ca_key.private_key_to_der().map_err(|err| {
+            error!(?err, "Unable to convert trust anchor key to DER");
+            OperationError::KP0009KeyObjectPrivateToDer
+        })?;
+
+        let (kid, ca_cert) = Self::build_anchor(&ca_key, valid_from)?;
+
+        self.active
+            .insert(valid_from, (ca_key.clone(), ca_cert.clone()));
+
+        self.all.insert(
+            kid,
+            InternalReplTrustAnchor {
+                valid_from,
+                status: InternalReplTrustAnchorStatus::Valid {
+                    ca_key,
+                    ca_cert,
+                    private_der,
+                },
+                status_cid: cid.clone(),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn revoke(&mut self, revoke_key_id: &KeyId, cid: &Cid) -> Result<bool, OperationError> {
+        if let Some(key_to_revoke) = self.all.get_mut(revoke_key_id) {
+            key_to_revoke.status = InternalReplTrustAnchorStatus::Revoked;
+            key_to_revoke.status_cid = cid.clone();
+
+            let valid_from = key_to_revoke.valid_from;
+
+            // Remove it from the active set.
+            self.active.remove(&valid_from);
+
+            Ok(true)
+        } else {
+            // We didn't revoke anything
+            Ok(false)
+        }
+    }
+
+    fn load(
+        &mut self,
+        id: &str,
+        status: KeyStatus,
+        status_cid: Cid,
+        der: &[u8],
+        valid_from: u64,
+    ) -> Result<(), OperationError> {
+        let id: KeyId = id.to_string();
+
+        let status = match status {
+            KeyStatus::Valid | KeyStatus::Retained => {
+                let ca_key = PKey::private_key_from_der(der).map_err(|err| {
+                    error!(?err, ?id, "Unable to load replication trust anchor key");
+                    OperationError::KP0048KeyObjectReplTrustAnchorInvalid
+                })?;
+
+                let (_kid, ca_cert) = Self::build_anchor(&ca_key, valid_from)?;
+
+                if status == KeyStatus::Valid {
+                    self.active
+                        .insert(valid_from, (ca_key.clone(), ca_cert.clone()));
+
+                    InternalReplTrustAnchorStatus::Valid {
+                        ca_key,
+                        ca_cert,
+                        private_der: der.to_vec(),
+                    }
+                } else {
+                    InternalReplTrustAnchorStatus::Retained {
+                        ca_cert,
+                        private_der: der.to_vec(),
+                    }
+                }
+            }
+            KeyStatus::Revoked => InternalReplTrustAnchorStatus::Revoked,
+        };
+
+        self.all.insert(
+            id,
+            InternalReplTrustAnchor {
+                valid_from,
+                status,
+                status_cid,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn to_key_iter(&self) -> impl Iterator<Item = (KeyId, KeyInternalData)> + '_ {
+        self.all.iter().map(|(key_id, internal_anchor)| {
+            let usage = KeyUsage::ReplTrustAnchor;
+
+            let valid_from = internal_anchor.valid_from;
+            let status_cid = internal_anchor.status_cid.clone();
+
+            let (status, der) = match &internal_anchor.status {
+                InternalReplTrustAnchorStatus::Valid { private_der, .. } => {
+                    (KeyStatus::Valid, private_der.clone())
+                }
+                InternalReplTrustAnchorStatus::Retained { private_der, .. } => {
+                    (KeyStatus::Retained, private_der.clone())
+                }
+                InternalReplTrustAnchorStatus::Revoked => {
+                    (KeyStatus::Revoked, Vec::with_capacity(0))
+                }
+            };
+
+            (
+                key_id.clone(),
+                KeyInternalData {
+                    usage,
+                    valid_from,
+                    der,
+                    status,
+                    status_cid,
+                },
+            )
+        })
+    }
+
+    fn trust_anchors(&self) -> Vec<X509> {
+        self.all
+            .values()
+            .filter_map(|internal_anchor| match &internal_anchor.status {
+                InternalReplTrustAnchorStatus::Valid { ca_cert, .. }
+                | InternalReplTrustAnchorStatus::Retained { ca_cert, .. } => Some(ca_cert.clone()),
+                InternalReplTrustAnchorStatus::Revoked => None,
+            })
+            .collect()
+    }
+
+    fn issue(
+        &self,
+        server_uuid: Uuid,
+        domain_name: &str,
+        current_time: Duration,
+    ) -> Result<(PKey<Private>, X509), OperationError> {
+        let Some((ca_key, ca_cert)) = self.get_valid_anchor(current_time) else {
+            error!("No replication trust anchors available. This may indicate that no anchors are valid yet!");
+            return Err(OperationError::KP0049KeyObjectNoActiveReplTrustAnchor);
+        };
+
+        build_replication_identity(
+            ca_key,
+            ca_cert,
+            server_uuid,
+            domain_name,
+            REPL_MTLS_IDENTITY_DAYS,
+        )
+        .map_err(|err| {
+            error!(?err, "Unable to issue replication identity");
+            OperationError::KP0050KeyObjectReplIdentityIssue
+        })
+    }
+}
+
+#[derive(Clone)]
+enum InternalSshCaStatus {
+    Valid {
+        ca_key: PKey<Private>,
+        private_der: Vec<u8>,
+    },
+    Retained {
+        ca_key: PKey<Private>,
+        private_der: Vec<u8>,
+    },
+    Revoked,
+}
+
+#[derive(Clone)]
+struct InternalSshCa {
+    valid_from: u64,
+    status: InternalSshCaStatus,
+    status_cid: Cid,
+}
+
+/// The certificate authorities that issue ssh user certificates. Certificates from retained
+/// authorities remain trusted until they expire, so their public keys are still published.
+#[derive(Default, Clone)]
+struct KeyObjectInternalSshCa {
+    // active authorities are in a BTreeMap indexed by their valid_from time so that we
+    // can retrieve the authority that issues new certificates.
+    active: BTreeMap<u64, PKey<Private>>,
+
+    // All authorities are stored by their KeyId.
+    all: BTreeMap<KeyId, InternalSshCa>,
+}
+
+impl KeyObjectInternalSshCa {
+    fn key_id(ca_key: &PKey<Private>) -> Result<KeyId, OperationError> {
+        ca_key
+            .public_key_to_der()
+            .map(|public_der| hex::encode(sha::sha256(&public_der)))
+            .map_err(|err| {
+                error!(
+                    ?err,
+                    "Unable to convert ssh certificate authority public key to DER"
+                );
+                OperationError::KP0054KeyObjectSshCaInvalid
+            })
+    }
+
+    fn get_valid_ca(&self, time: Duration) -> Option<&PKey<Private>> {
+        let ct_secs = time.as_secs();
+
+        self.active
+            .range((Unbounded, Included(ct_secs)))
+            .next_back()
+            .map(|(_time, ca_key)| ca_key)
+    }
+
+    fn assert_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        if self.get_valid_ca(valid_from).is_none() {
+            // This means there is no active authority, so we need to create one.
+            warn!("no active ssh certificate authority found, creating a new one ...");
+            self.new_active(valid_from, cid)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn new_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        let valid_from = valid_from.as_secs();
+
+        let ca_key = get_group()
+            .and_then(|group| EcKey::generate(&group))
+            .and_then(PKey::from_ec_key)
+            .map_err(|err| {
+                error!(?err, "Unable to generate new ssh certificate authority key");
+                OperationError::KP0053KeyObjectSshCaGeneration
+            })?;
+
+        let private_der = ca_key.private_key_to_der().map_err(|err| {
+            error!(
+                ?err,
+                "Unable to convert ssh certificate authority key to DER"
+            );
+            OperationError::KP0009KeyObjectPrivateToDer
+        })?;
+
+        let kid = Self::key_id(&ca_key)?;
+
+        self.active.insert(valid_from, ca_key.clone());
+
+        self.all.insert(
+            kid,
+            InternalSshCa {
+                valid_from,
+                status: InternalSshCaStatus::Valid {
+                    ca_key,
+                    private_der,
+                },
+                status_cid: cid.clone(),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn revoke(&mut self, revoke_key_id: &KeyId, cid: &Cid) -> Result<bool, OperationError> {
+        if let Some(key_to_revoke) = self.all.get_mut(revoke_key_id) {
+            key_to_revoke.status = InternalSshCaStatus::Revoked;
+            key_to_revoke.status_cid = cid.clone();
+
+            let valid_from = key_to_revoke.valid_from;
+
+            // Remove it from the active set.
+            self.active.remove(&valid_from);
+
+            Ok(true)
+        } else {
+            // We didn't revoke anything
+            Ok(false)
+        }
+    }
+
+    fn load(
+        &mut self,
+        id: &str,
+        status: KeyStatus,
+        status_cid: Cid,
+        der: &[u8],
+        valid_from: u64,
+    ) -> Result<(), OperationError> {
+        let id: KeyId = id.to_string();
+
+        let status = match status {
+            KeyStatus::Valid | KeyStatus::Retained => {
+                let ca_key = PKey::private_key_from_der(der).map_err(|err| {
+                    error!(?err, ?id, "Unable to load ssh certificate authority key");
+                    OperationError::KP0054KeyObjectSshCaInvalid
+                })?;
+
+                if status == KeyStatus::Valid {
+                    self.active.insert(valid_from, ca_key.clone());
+
+                    InternalSshCaStatus::Valid {
+                        ca_key,
+                        private_der: der.to_vec(),
+                    }
+                } else {
+                    InternalSshCaStatus::Retained {
+                        ca_key,
+                        private_der: der.to_vec(),
+                    }
+                }
+            }
+            KeyStatus::Revoked => InternalSshCaStatus::Revoked,
+        };
+
+        self.all.insert(
+            id,
+            InternalSshCa {
+                valid_from,
+                status,
+                status_cid,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn to_key_iter(&self) -> impl Iterator<Item = (KeyId, KeyInternalData)> + '_ {
+        self.all.iter().map(|(key_id, internal_ca)| {
+            let usage = KeyUsage::SshCa;
+
+            let valid_from = internal_ca.valid_from;
+            let status_cid = internal_ca.status_cid.clone();
+
+            let (status, der) = match &internal_ca.status {
+                InternalSshCaStatus::Valid { private_der, .. } => {
+                    (KeyStatus::Valid, private_der.clone())
+                }
+                InternalSshCaStatus::Retained { private_der, .. } => {
+                    (KeyStatus::Retained, private_der.clone())
+                }
+                InternalSshCaStatus::Revoked => (KeyStatus::Revoked, Vec::with_capacity(0)),
+            };
+
+            (
+                key_id.clone(),
+                KeyInternalData {
+                    usage,
+                    valid_from,
+                    der,
+                    status,
+                    status_cid,
+                },
+            )
+        })
+    }
+
+    fn trusted_keys(&self) -> impl Iterator<Item = &PKey<Private>> + '_ {
+        self.all
+            .values()
+            .filter_map(|internal_ca| match &internal_ca.status {
+                InternalSshCaStatus::Valid { ca_key, .. }
+                | InternalSshCaStatus::Retained { ca_key, .. } => Some(ca_key),
+                InternalSshCaStatus::Revoked => None,
+            })
+    }
+
+    fn public_keys(&self) -> Result<Vec<String>, OperationError> {
+        self.trusted_keys()
+            .map(|ca_key| {
+                ssh_ca_public_key(ca_key).map_err(|err| {
+                    error!(
+                        ?err,
+                        "Unable to encode ssh certificate authority public key"
+                    );
+                    OperationError::KP0054KeyObjectSshCaInvalid
+                })
+            })
+            .collect()
+    }
+
+    fn is_issued_by_trusted(&self, certificate: &str) -> bool {
+        self.trusted_keys()
+            .any(|ca_key| is_ssh_certificate_issued_by(certificate, ca_key))
+    }
+
+    fn issue(
+        &self,
+        public_key: &str,
+        key_id: &str,
+        principals: &[String],
+        valid_after: Duration,
+        valid_before: Duration,
+    ) -> Result<(u64, String), OperationError> {
+        let Some(ca_key) = self.get_valid_ca(valid_after) else {
+            error!("No ssh certificate authorities available. This may indicate that no authorities are valid yet!");
+            return Err(OperationError::KP0055KeyObjectNoActiveSshCa);
+        };
+
+        build_ssh_user_certificate(
+            ca_key,
+            public_key,
+            key_id,
+            principals,
+            valid_after.as_secs(),
+            valid_before.as_secs(),
+        )
+        .map_err(|err| {
+            debug!(?err, "Unable to issue ssh user certificate");
+            OperationError::KP0056KeyObjectSshCertificateIssue
+        })
+    }
+}
+
+#[derive(Clone)]
+enum InternalSaml2SigningStatus {
+    Valid {
+        signing_key: PKey<Private>,
+        certificate: X509,
+        private_der: Vec<u8>,
+    },
+    Retained {
+        certificate: X509,
+        private_der: Vec<u8>,
+    },
+    Revoked,
+}
+
+#[derive(Clone)]
+struct InternalSaml2Signing {
+    valid_from: u64,
+    status: InternalSaml2SigningStatus,
+    status_cid: Cid,
+}
+
+/// The keys that sign saml2 assertions. Service providers may still hold assertions signed
+/// by a retained key, so the certificates of retained keys are still published.
+#[derive(Default, Clone)]
+struct KeyObjectInternalSaml2Signing {
+    // active keys are in a BTreeMap indexed by their valid_from time so that we
+    // can retrieve the key that signs new assertions.
+    active: BTreeMap<u64, (PKey<Private>, X509)>,
+
+    // All keys are stored by their KeyId.
+    all: BTreeMap<KeyId, InternalSaml2Signing>,
+}
+
+impl KeyObjectInternalSaml2Signing {
+    /// Build the certificate of a signing key. The key id is derived from the public key, so
+    /// that every server derives the same id and certificate for the same key.
+    fn build_certificate(
+        signing_key: &PKey<Private>,
+        valid_from: u64,
+    ) -> Result<(KeyId, X509), OperationError> {
+        let public_der = signing_key.public_key_to_der().map_err(|err| {
+            error!(?err, "Unable to convert saml2 signing public key to DER");
+            OperationError::KP0059KeyObjectSaml2SigningInvalid
+        })?;
+
+        let kid = hex::encode(sha::sha256(&public_der));
+
+        let certificate = build_saml2_signing_certificate(signing_key, &kid, valid_from as i64)
+            .map_err(|err| {
+                error!(?err, "Unable to build saml2 signing certificate");
+                OperationError::KP0059KeyObjectSaml2SigningInvalid
+            })?;
+
+        Ok((kid, certificate))
+    }
+
+    fn get_valid_key(&self, time: Duration) -> Option<&(PKey<Private>, X509)> {
+        let ct_secs = time.as_secs();
+
+        self.active
+            .range((Unbounded, Included(ct_secs)))
+            .next_back()
+            .map(|(_time, signing_key)| signing_key)
+    }
+
+    fn assert_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        if self.get_valid_key(valid_from).is_none() {
+            // This means there is no active key, so we need to create one.
+            warn!("no active saml2 signing key found, creating a new one ...");
+            self.new_active(valid_from, cid)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn new_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        let valid_from = valid_from.as_secs();
+
+        let signing_key = generate_saml2_signing_key().map_err(|err| {
+            error!(?err, "Unable to generate new saml2 signing key");
+            OperationError::KP0058KeyObjectSaml2SigningGeneration
+        })?;
+
+        let private_der = signing_key.private_key_to_der().map_err(|err| {
+            error!(?err, "Unable to convert saml2 signing key to DER");
+            OperationError::KP0009KeyObjectPrivateToDer
+        })?;
+
+        let (kid, certificate) = Self::build_certificate(&signing_key, valid_from)?;
+
+        self.active
+            .insert(valid_from, (signing_key.clone(), certificate.clone()));
+
+        self.all.insert(
+            kid,
+            InternalSaml2Signing {
+                valid_from,
+                status: InternalSaml2SigningStatus::Valid {
+                    signing_key,
+                    certificate,
+                    private_der,
+                },
+                status_cid: cid.clone(),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn revoke(&mut self, revoke_key_id: &KeyId, cid: &Cid) -> Result<bool, OperationError> {
+        if let Some(key_to_revoke) = self.all.get_mut(revoke_key_id) {
+            key_to_revoke.status = InternalSaml2SigningStatus::Revoked;
+            key_to_revoke.status_cid = cid.clone();
+
+            let valid_from = key_to_revoke.valid_from;
+
+            // Remove it from the active set.
+            self.active.remove(&valid_from);
+
+            Ok(true)
+        } else {
+            // We didn't revoke anything
+            Ok(false)
+        }
+    }
+
+    fn load(
+        &mut self,
+        id: &str,
+        status: KeyStatus,
+        status_cid: Cid,
+        der: &[u8],
+        valid_from: u64,
+    ) -> Result<(), OperationError> {
+        let id: KeyId = id.to_string();
+
+        let status = match status {
+            KeyStatus::Valid | KeyStatus::Retained => {
+                let signing_key = PKey::private_key_from_der(der).map_err(|err| {
+                    error!(?err, ?id, "Unable to load saml2 signing key");
+                    OperationError::KP0059KeyObjectSaml2SigningInvalid
+                })?;
+
+                let (_kid, certificate) = Self::build_certificate(&signing_key, valid_from)?;
+
+                if status == KeyStatus::Valid {
+                    self.active
+                        .insert(valid_from, (signing_key.clone(), certificate.clone()));
+
+                    InternalSaml2SigningStatus::Valid {
+                        signing_key,
+                        certificate,
+                        private_der: der.to_vec(),
+                    }
+                } else {
+                    InternalSaml2SigningStatus::Retained {
+                        certificate,
+                        private_der: der.to_vec(),
+                    }
+                }
+            }
+            KeyStatus::Revoked => InternalSaml2SigningStatus::Revoked,
+        };
+
+        self.all.insert(
+            id,
+            InternalSaml2Signing {
+                valid_from,
+                status,
+                status_cid,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn to_key_iter(&self) -> impl Iterator<Item = (KeyId, KeyInternalData)> + '_ {
+        self.all.iter().map(|(key_id, internal_signing)| {
+            let usage = KeyUsage::Saml2Signing;
+
+            let valid_from = internal_signing.valid_from;
+            let status_cid = internal_signing.status_cid.clone();
+
+            let (status, der) = match &internal_signing.status {
+                InternalSaml2SigningStatus::Valid { private_der, .. } => {
+                    (KeyStatus::Valid, private_der.clone())
+                }
+                InternalSaml2SigningStatus::Retained { private_der, .. } => {
+                    (KeyStatus::Retained, private_der.clone())
+                }
+                InternalSaml2SigningStatus::Revoked => (KeyStatus::Revoked, Vec::with_capacity(0)),
+            };
+
+            (
+                key_id.clone(),
+                KeyInternalData {
+                    usage,
+                    valid_from,
+                    der,
+                    status,
+                    status_cid,
+                },
+            )
+        })
+    }
+
+    fn certificates(&self) -> Vec<X509> {
+        self.all
+            .values()
+            .filter_map(|internal_signing| match &internal_signing.status {
+                InternalSaml2SigningStatus::Valid { certificate, .. }
+                | InternalSaml2SigningStatus::Retained { certificate, .. } => {
+                    Some(certificate.clone())
+                }
+                InternalSaml2SigningStatus::Revoked => None,
+            })
+            .collect()
+    }
+
+    fn sign(&self, data: &[u8], current_time: Duration) -> Result<(X509, Vec<u8>), OperationError> {
+        let Some((signing_key, certificate)) = self.get_valid_key(current_time) else {
+            error!(
+                "No saml2 signing keys available. This may indicate that no keys are valid yet!"
+            );
+            return Err(OperationError::KP0060KeyObjectNoActiveSaml2Signing);
+        };
+
+        saml2_sign(signing_key, data)
+            .map(|signature| (certificate.clone(), signature))
+            .map_err(|err| {
+                error!(?err, "Unable to sign with saml2 signing key");
+                OperationError::KP0059KeyObjectSaml2SigningInvalid
+            })
+    }
+}
+
+#[derive(Clone)]
+enum InternalKerberosStatus {
+    Valid { secret: Vec<u8> },
+    Retained { secret: Vec<u8> },
+    Revoked,
+}
+
+#[derive(Clone)]
+struct InternalKerberos {
+    valid_from: u64,
+    status: InternalKerberosStatus,
+    status_cid: Cid,
+}
+
+/// The secrets that the long term keys of kerberos services are derived from. Services may
+/// still be presented tickets that were issued before a rotation, so the keys derived from
+/// retained secrets are still given to them.
+#[derive(Default, Clone)]
+struct KeyObjectInternalKerberos {
+    // active secrets are in a BTreeMap indexed by their valid_from time so that we
+    // can retrieve the secret that issues new tickets.
+    active: BTreeMap<u64, Vec<u8>>,
+
+    // All secrets are stored by their KeyId.
+    all: BTreeMap<KeyId, InternalKerberos>,
+}
+
+impl KeyObjectInternalKerberos {
+    fn key_id(secret: &[u8]) -> KeyId {
+        hex::encode(sha::sha256(secret))
+    }
+
+    /// Tickets refer to the key that encrypts them by a version number, which is derived
+    /// from the time the secret is valid from so that it is the same on every replica.
+    fn kvno(valid_from: u64) -> u32 {
+        (valid_from as u32).max(1)
+    }
+
+    fn service_key(
+        secret: &[u8],
+        valid_from: u64,
+        service: &KerberosPrincipal,
+    ) -> Result<KerberosServiceKey, OperationError> {
+        kerberos_derive_service_key(secret, service)
+            .map(|key| KerberosServiceKey {
+                kvno: Self::kvno(valid_from),
+                key,
+            })
+            .map_err(|err| {
+                error!(?err, "Unable to derive kerberos service key");
+                OperationError::KP0062KeyObjectKerberosInvalid
+            })
+    }
+
+    fn get_valid_secret(&self, time: Duration) -> Option<(&u64, &Vec<u8>)> {
+        let ct_secs = time.as_secs();
+
+        self.active
+            .range((Unbounded, Included(ct_secs)))
+            .next_back()
+    }
+
+    fn assert_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        if self.get_valid_secret(valid_from).is_none() {
+            // This means there is no active secret, so we need to create one.
+            warn!("no active kerberos secret found, creating a new one ...");
+            self.new_active(valid_from, cid)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn new_active(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        let valid_from = valid_from.as_secs();
+
+        let secret = kerberos_generate_key();
+        let kid = Self::key_id(&secret);
+
+        self.active.insert(valid_from, secret.clone());
+
+        self.all.insert(
+            kid,
+            InternalKerberos {
+                valid_from,
+                status: InternalKerberosStatus::Valid { secret },
+                status_cid: cid.clone(),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn revoke(&mut self, revoke_key_id: &KeyId, cid: &Cid) -> Result<bool, OperationError> {
+        if let Some(key_to_revoke) = self.all.get_mut(revoke_key_id) {
+            key_to_revoke.status = InternalKerberosStatus::Revoked;
+            key_to_revoke.status_cid = cid.clone();
+
+            let valid_from = key_to_revoke.valid_from;
+
+            // Remove it from the active set.
+            self.active.remove(&valid_from);
+
+            Ok(true)
+        } else {
+            // We didn't revoke anything
+            Ok(false)
+        }
+    }
+
+    fn load(
+        &mut self,
+        id: &str,
+        status: KeyStatus,
+        status_cid: Cid,
+        der: &[u8],
+        valid_from: u64,
+    ) -> Result<(), OperationError> {
+        let id: KeyId = id.to_string();
+
+        let status = match status {
+            KeyStatus::Valid | KeyStatus::Retained => {
+                if der.len() != KRB5_AES256_KEY_LEN {
+                    error!(?id, "Unable to load kerberos secret, invalid length");
+                    return Err(OperationError::KP0062KeyObjectKerberosInvalid);
+                }
+
+                let secret = der.to_vec();
+
+                if status == KeyStatus::Valid {
+                    self.active.insert(valid_from, secret.clone());
+                    InternalKerberosStatus::Valid { secret }
+                } else {
+                    InternalKerberosStatus::Retained { secret }
+                }
+            }
+            KeyStatus::Revoked => InternalKerberosStatus::Revoked,
+        };
+
+        self.all.insert(
+            id,
+            InternalKerberos {
+                valid_from,
+                status,
+                status_cid,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn to_key_iter(&self) -> impl Iterator<Item = (KeyId, KeyInternalData)> + '_ {
+        self.all.iter().map(|(key_id, internal_kerberos)| {
+            let usage = KeyUsage::Kerberos;
+
+            let valid_from = internal_kerberos.valid_from;
+            let status_cid = internal_kerberos.status_cid.clone();
+
+            let (status, der) = match &internal_kerberos.status {
+                InternalKerberosStatus::Valid { secret } => (KeyStatus::Valid, secret.clone()),
+                InternalKerberosStatus::Retained { secret } => {
+                    (KeyStatus::Retained, secret.clone())
+                }
+                InternalKerberosStatus::Revoked => (KeyStatus::Revoked, Vec::with_capacity(0)),
+            };
+
+            (
+                key_id.clone(),
+                KeyInternalData {
+                    usage,
+                    valid_from,
+                    der,
+                    status,
+                    status_cid,
+                },
+            )
+        })
+    }
+
+    fn service_keys(
+        &self,
+        service: &KerberosPrincipal,
+    ) -> Result<Vec<KerberosServiceKey>, OperationError> {
+        self.all
+            .values()
+            .filter_map(|internal_kerberos| match &internal_kerberos.status {
+                InternalKerberosStatus::Valid { secret }
+                | InternalKerberosStatus::Retained { secret } => Some(Self::service_key(
+                    secret,
+                    internal_kerberos.valid_from,
+                    service,
+                )),
+                InternalKerberosStatus::Revoked => None,
+            })
+            .collect()
+    }
+
+    fn active_service_key(
+        &self,
+        service: &KerberosPrincipal,
+        current_time: Duration,
+    ) -> Result<KerberosServiceKey, OperationError> {
+        let Some((valid_from, secret)) = self.get_valid_secret(current_time) else {
+            error!(
+                "No kerberos secrets available. This may indicate that no secrets are valid yet!"
+            );
+            return Err(OperationError::KP0063KeyObjectNoActiveKerberosKey);
+        };
+
+        Self::service_key(secret, *valid_from, service)
+    }
+
+    fn issue(
+        &self,
+        client: &KerberosPrincipal,
+        service: &KerberosPrincipal,
+        auth_time: Duration,
+        end_time: Duration,
+    ) -> Result<KerberosCredential, OperationError> {
+        let service_key = self.active_service_key(service, auth_time)?;
+
+        kerberos_issue_ticket(
+            client,
+            service,
+            &service_key,
+            auth_time.as_secs(),
+            end_time.as_secs(),
+        )
+        .map_err(|err| {
+            error!(?err, "Unable to issue kerberos ticket");
+            OperationError::KP0064KeyObjectKerberosTicketIssue
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct KeyObjectInternal {
+    provider: Arc<KeyProviderInternal>,
+    uuid: Uuid,
+    jws_es256: Option<KeyObjectInternalJwtEs256>,
+    jws_rs256: Option<KeyObjectInternalJwtRs256>,
+    jwe_a128gcm: Option<KeyObjectInternalJweA128GCM>,
+    repl_trust_anchor: Option<KeyObjectInternalReplTrustAnchor>,
+    ssh_ca: Option<KeyObjectInternalSshCa>,
+    saml2_signing: Option<KeyObjectInternalSaml2Signing>,
+    kerberos: Option<KeyObjectInternalKerberos>,
+    // If you add more types here you need to add these to rotate
+    // and revoke.
+}
+
+#[cfg(test)]
+impl KeyObjectInternal {
+    pub fn new_test() -> Arc<KeyObject> {
+        let provider = Arc::new(KeyProviderInternal::create_test_provider());
+
+        let mut key_object = provider
+            .create_new_key_object(Uuid::new_v4(), provider.clone())
+            .expect("Unable to build new key object");
+
+        key_object
+            .jws_es256_assert(Duration::from_secs(0), &Cid::new_zero())
+            .expect("Unable to add jws_es256 to key object");
+
+        Arc::new(key_object)
+    }
+}
+
+impl KeyObjectT for KeyObjectInternal {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn duplicate(&self) -> KeyObject {
+        Box::new(self.clone())
+    }
+
+    fn rotate_keys(&mut self, rotation_time: Duration, cid: &Cid) -> Result<(), OperationError> {
+        if let Some(jws_es256_object) = &mut self.jws_es256 {
+            jws_es256_object.new_active(rotation_time, cid)?;
+        }
+
+        if let Some(jws_rs256_object) = &mut self.jws_rs256 {
+            jws_rs256_object.new_active(rotation_time, cid)?;
+        }
+
+        if let Some(jwe_a128_gcm) = &mut self.jwe_a128gcm {
+            jwe_a128_gcm.new_active(rotation_time, cid)?;
+        }
+
+        if let Some(repl_trust_anchor) = &mut self.repl_trust_anchor {
+            repl_trust_anchor.new_active(rotation_time, cid)?;
+        }
+
+        if let Some(ssh_ca) = &mut self.ssh_ca {
+            ssh_ca.new_active(rotation_time, cid)?;
+        }
+
+        if let Some(saml2_signing) = &mut self.saml2_signing {
+            saml2_signing.new_active(rotation_time, cid)?;
+        }
+
+        if let Some(kerberos) = &mut self.kerberos {
+            kerberos.new_active(rotation_time, cid)?;
+        }
+
+        Ok(())
+    }
+
+    fn revoke_keys(
+        &mut self,
+        revoke_set: &BTreeSet<String>,
+        cid: &Cid,
+    ) -> Result<(), OperationError> {
+        for revoke_key_id in revoke_set.iter() {
+            let mut has_revoked = false;
+
+            if let Some(jws_es256_object) = &mut self.jws_es256 {
+                if jws_es256_object.revoke(revoke_key_id, cid)? {
+                    has_revoked = true;
+                }
+            };
+
+            if let Some(jws_rs256_object) = &mut self.jws_rs256 {
+                if jws_rs256_object.revoke(revoke_key_id, cid)? {
+                    has_revoked = true;
+                }
+            };
+
+            if let Some(jwe_a128_gcm) = &mut self.jwe_a128gcm {
+                if jwe_a128_gcm.revoke(revoke_key_id, cid)? {
+                    has_revoked = true;
+                }
+            };
+
+            if let Some(repl_trust_anchor) = &mut self.repl_trust_anchor {
+                if repl_trust_anchor.revoke(revoke_key_id, cid)? {
+                    has_revoked = true;
+                }
+            };
+
+            if let Some(ssh_ca) = &mut self.ssh_ca {
+                if ssh_ca.revoke(revoke_key_id, cid)? {
+                    has_revoked = true;
+                }
+            };
+
+            if let Some(saml2_signing) = &mut self.saml2_signing {
+                if saml2_signing.revoke(revoke_key_id, cid)? {
+                    has_revoked = true;
+                }
+            };
+
+            if let Some(kerberos) = &mut self.kerberos {
+                if kerberos.revoke(revoke_key_id, cid)? {
+                    has_revoked = true;
+                }
+            };
+
+            if !has_revoked {
+                error!(?revoke_key_id, "Unable to revoked key, id not found");
+                return Err(OperationError::KP0026KeyObjectNoSuchKey);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn jws_es256_sign(
+        &self,
+        jws: &Jws,
+        current_time: Duration,
+    ) -> Result<JwsCompact, OperationError> {
+        if let Some(jws_es256_object) = &self.jws_es256 {
+            jws_es256_object.sign(jws, current_time)
+        } else {
+            error!(provider_uuid = ?self.uuid, "jwt es256 not available on this provider");
+            Err(OperationError::KP0017KeyProviderNoSuchKey)
+        }
+    }
+
+    fn jws_verify(&self, jwsc: &JwsCompact) -> Result<Jws, OperationError> {
+        // Seems weird, but we can match on alg to select the provider.
+        let alg = jwsc.alg();
+
+        match alg {
+            JwaAlg::ES256 => {
+                if let Some(jws_es256_object) = &self.jws_es256 {
+                    jws_es256_object.verify(jwsc)
+                } else {
+                    error!(provider_uuid = ?self.uuid, "jwt es256 not available on this provider");
+                    Err(OperationError::KP0018KeyProviderNoSuchKey)
+                }
+            }
+            JwaAlg::RS256 => {
+                if let Some(jws_rs256_object) = &self.jws_rs256 {
+                    jws_rs256_object.verify(jwsc)
+                } else {
+                    error!(provider_uuid = ?self.uuid, "jwt rs256 not available on this provider");
+                    Err(OperationError::KP0018KeyProviderNoSuchKey)
+                }
+            }
+            unsupported_alg => {
+                // unsupported rn.
+                error!(provider_uuid = ?self.uuid, ?unsupported_alg, "algorithm not available on this provider");
+                Err(OperationError::KP0019KeyProviderUnsupportedAlgorithm)
+            }
+        }
+    }
+
+    fn jws_public_jwk(&self, kid: &str) -> Result<Option<Jwk>, OperationError> {
+        if let Some(jws_es256_object) = &self.jws_es256 {
+            if let Some(jwk) = jws_es256_object.public_jwk(kid)? {
+                return Ok(Some(jwk));
+            }
+        }
+
+        Ok(self
+            .jws_rs256
+            .as_ref()
+            .and_then(|jws_rs256_object| jws_rs256_object.public_jwk(kid)))
+    }
+
+    fn jws_public_jwks(&self) -> Result<Vec<Jwk>, OperationError> {
+        let mut jwks = self
+            .jws_es256
+            .as_ref()
+            .map(|jws_es256_object| jws_es256_object.public_jwks())
+            .transpose()?
+            .unwrap_or_default();
+
+        if let Some(jws_rs256_object) = &self.jws_rs256 {
+            jwks.extend(jws_rs256_object.public_jwks());
+        }
+
+        Ok(jwks)
+    }
+
+    fn jws_rs256_assert(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        let koi = self
+            .jws_rs256
+            .get_or_insert_with(KeyObjectInternalJwtRs256::default);
+
+        koi.assert_active(valid_from, cid)
+    }
+
+    fn jws_rs256_sign(
+        &self,
+        jws: &Jws,
+        current_time: Duration,
+    ) -> Result<JwsCompact, OperationError> {
+        if let Some(jws_rs256_object) = &self.jws_rs256 {
+            jws_rs256_object.sign(jws, current_time)
+        } else {
+            error!(provider_uuid = ?self.uuid, "jwt rs256 not available on this provider");
+            Err(OperationError::KP0070KeyObjectNoJwsSigningKey)
+        }
+    }
+
+    fn jws_es256_import(
+        &mut self,
+        import_keys: &SmolSet<[Vec<u8>; 1]>,
+        valid_from: Duration,
+        cid: &Cid,
+    ) -> Result<(), OperationError> {
+        let koi = self
+            .jws_es256
+            .get_or_insert_with(KeyObjectInternalJwtEs256::default);
+
+        koi.import(import_keys, valid_from, cid)
+    }
+
+    fn jws_es256_assert(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        let koi = self
+            .jws_es256
+            .get_or_insert_with(KeyObjectInternalJwtEs256::default);
+
+        koi.assert_active(valid_from, cid)
+    }
+
+    fn self_test(&self, current_time: Duration) -> Result<(), OperationError> {
+        const SELF_TEST_PAYLOAD: &[u8] = b"kanidm key object self test";
+
+        if self.jws_es256.is_some() {
+            let jws = JwsBuilder::from(SELF_TEST_PAYLOAD.to_vec()).build();
+            let jwsc = self.jws_es256_sign(&jws, current_time)?;
+            let released = self.jws_verify(&jwsc)?;
+            if released.payload() != SELF_TEST_PAYLOAD {
+                error!(key_object_uuid = ?self.uuid, "jws es256 self test payload mismatch");
+                return Err(OperationError::KP0045KeyObjectSelfTestJwsMismatch);
+            }
+        }
+
+        if self.jws_rs256.is_some() {
+            let jws = JwsBuilder::from(SELF_TEST_PAYLOAD.to_vec()).build();
+            let jwsc = self.jws_rs256_sign(&jws, current_time)?;
+            let released = self.jws_verify(&jwsc)?;
+            if released.payload() != SELF_TEST_PAYLOAD {
+                error!(key_object_uuid = ?self.uuid, "jws rs256 self test payload mismatch");
+                return Err(OperationError::KP0045KeyObjectSelfTestJwsMismatch);
+            }
+        }
+
+        if self.jwe_a128gcm.is_some() {
+            let jwe = JweBuilder::from(SELF_TEST_PAYLOAD.to_vec()).build();
+            let jwec = self.jwe_a128gcm_encrypt(&jwe, current_time)?;
+            let released = self.jwe_decrypt(&jwec)?;
+            if released.payload() != SELF_TEST_PAYLOAD {
+                error!(key_object_uuid = ?self.uuid, "jwe a128gcm self test payload mismatch");
+                return Err(OperationError::KP0046KeyObjectSelfTestJweMismatch);
+            }
+        }
+
+        if let Some(repl_trust_anchor) = &self.repl_trust_anchor {
+            let (_key, identity) =
+                repl_trust_anchor.issue(self.uuid, "self-test.invalid", current_time)?;
+            let verified = repl_trust_anchor.trust_anchors().iter().any(|anchor| {
+                anchor
+                    .public_key()
+                    .and_then(|public_key| identity.verify(&public_key))
+                    .unwrap_or(false)
+            });
+            if !verified {
+                error!(key_object_uuid = ?self.uuid, "replication identity self test did not verify");
+                return Err(OperationError::KP0052KeyObjectSelfTestReplIdentityInvalid);
+            }
+        }
+
+        if let Some(ssh_ca) = &self.ssh_ca {
+            // Certify the key of the authority, since we have no other key to hand.
+            let public_key = ssh_ca
+                .get_valid_ca(current_time)
+                .ok_or(OperationError::KP0055KeyObjectNoActiveSshCa)
+                .and_then(|ca_key| {
+                    ssh_ca_public_key(ca_key)
+                        .map_err(|_| OperationError::KP0054KeyObjectSshCaInvalid)
+                })?;
+            let (_serial, certificate) = ssh_ca.issue(
+                &public_key,
+                "self-test",
+                &[],
+                current_time,
+                current_time + Duration::from_secs(60),
+            )?;
+            if !ssh_ca.is_issued_by_trusted(&certificate) {
+                error!(key_object_uuid = ?self.uuid, "ssh certificate self test did not verify");
+                return Err(OperationError::KP0057KeyObjectSelfTestSshCertificateInvalid);
+            }
+        }
+
+        if let Some(saml2_signing) = &self.saml2_signing {
+            let (certificate, signature) = saml2_signing.sign(SELF_TEST_PAYLOAD, current_time)?;
+            if !saml2_verify(&certificate, SELF_TEST_PAYLOAD, &signature) {
+                error!(key_object_uuid = ?self.uuid, "saml2 signature self test did not verify");
+                return Err(OperationError::KP0061KeyObjectSelfTestSaml2SignatureInvalid);
+            }
+        }
+
+        if let Some(kerberos) = &self.kerberos {
+            // Services decrypt with the keys from their keytab, which must be able to decrypt
+            // what was encrypted with the active key.
+            let service = KerberosPrincipal::service("host/self-test.invalid", "SELF-TEST.INVALID")
+                .ok_or(OperationError::KP0062KeyObjectKerberosInvalid)?;
+            let active_key = kerberos.active_service_key(&service, current_time)?;
+            let ciphertext = kerberos_encrypt(&active_key.key, 0, SELF_TEST_PAYLOAD)
+                .map_err(|_| OperationError::KP0062KeyObjectKerberosInvalid)?;
+            let decrypts = kerberos.service_keys(&service)?.iter().any(|service_key| {
+                service_key.kvno == active_key.kvno
+                    && kerberos_decrypt(&service_key.key, 0, &ciphertext)
+                        .map(|released| released == SELF_TEST_PAYLOAD)
+                        .unwrap_or(false)
+            });
+            if !decrypts {
+                error!(key_object_uuid = ?self.uuid, "kerberos service key self test did not decrypt");
+                return Err(OperationError::KP0065KeyObjectSelfTestKerberosKeyInvalid);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn jwe_decrypt(&self, jwec: &JweCompact) -> Result<Jwe, OperationError> {
+        let (alg, enc) = jwec.get_alg_enc();
+
+        match (alg, enc) {
+            (JweAlg::A128KW, JweEnc::A128GCM) => {
+                if let Some(jwe_a128_gcm) = &self.jwe_a128gcm {
+                    jwe_a128_gcm.decipher(jwec)
+                } else {
+                    error!(provider_uuid = ?self.uuid, "jwe a128gcm not available on this provider");
+                    Err(OperationError::KP0033KeyProviderNoSuchKey)
+                }
+            }
+            (unsupported_alg, unsupported_enc) => {
+                // unsupported rn.
+                error!(provider_uuid = ?self.uuid, ?unsupported_alg, ?unsupported_enc, "algorithm+encryption not available on this provider");
+                Err(OperationError::KP0019KeyProviderUnsupportedAlgorithm)
+            }
+        }
+    }
+
+    fn jwe_a128gcm_assert(
         &mut self,
         valid_from: Duration,
         cid: &Cid,
@@ -913,6 +2487,137 @@ impl KeyObjectT for KeyObjectInternal {
         }
     }
 
+    fn repl_trust_anchor_assert(
+        &mut self,
+        valid_from: Duration,
+        cid: &Cid,
+    ) -> Result<(), OperationError> {
+        let koi = self
+            .repl_trust_anchor
+            .get_or_insert_with(KeyObjectInternalReplTrustAnchor::default);
+
+        koi.assert_active(valid_from, cid)
+    }
+
+    fn repl_trust_anchors(&self) -> Result<Vec<X509>, OperationError> {
+        Ok(self
+            .repl_trust_anchor
+            .as_ref()
+            .map(|repl_trust_anchor| repl_trust_anchor.trust_anchors())
+            .unwrap_or_default())
+    }
+
+    fn repl_identity_issue(
+        &self,
+        server_uuid: Uuid,
+        domain_name: &str,
+        current_time: Duration,
+    ) -> Result<(PKey<Private>, X509), OperationError> {
+        if let Some(repl_trust_anchor) = &self.repl_trust_anchor {
+            repl_trust_anchor.issue(server_uuid, domain_name, current_time)
+        } else {
+            error!(provider_uuid = ?self.uuid, "replication trust anchor not available on this provider");
+            Err(OperationError::KP0051KeyProviderNoSuchKey)
+        }
+    }
+
+    fn ssh_ca_assert(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        let koi = self
+            .ssh_ca
+            .get_or_insert_with(KeyObjectInternalSshCa::default);
+
+        koi.assert_active(valid_from, cid)
+    }
+
+    fn ssh_ca_public_keys(&self) -> Result<Vec<String>, OperationError> {
+        self.ssh_ca
+            .as_ref()
+            .map(|ssh_ca| ssh_ca.public_keys())
+            .unwrap_or_else(|| Ok(Vec::with_capacity(0)))
+    }
+
+    fn ssh_user_certificate_issue(
+        &self,
+        public_key: &str,
+        key_id: &str,
+        principals: &[String],
+        valid_after: Duration,
+        valid_before: Duration,
+    ) -> Result<(u64, String), OperationError> {
+        if let Some(ssh_ca) = &self.ssh_ca {
+            ssh_ca.issue(public_key, key_id, principals, valid_after, valid_before)
+        } else {
+            error!(provider_uuid = ?self.uuid, "ssh certificate authority not available on this provider");
+            Err(OperationError::KP0055KeyObjectNoActiveSshCa)
+        }
+    }
+
+    fn saml2_signing_assert(
+        &mut self,
+        valid_from: Duration,
+        cid: &Cid,
+    ) -> Result<(), OperationError> {
+        let koi = self
+            .saml2_signing
+            .get_or_insert_with(KeyObjectInternalSaml2Signing::default);
+
+        koi.assert_active(valid_from, cid)
+    }
+
+    fn saml2_signing_certificates(&self) -> Result<Vec<X509>, OperationError> {
+        Ok(self
+            .saml2_signing
+            .as_ref()
+            .map(|saml2_signing| saml2_signing.certificates())
+            .unwrap_or_default())
+    }
+
+    fn saml2_sign(
+        &self,
+        data: &[u8],
+        current_time: Duration,
+    ) -> Result<(X509, Vec<u8>), OperationError> {
+        if let Some(saml2_signing) = &self.saml2_signing {
+            saml2_signing.sign(data, current_time)
+        } else {
+            error!(provider_uuid = ?self.uuid, "saml2 signing not available on this provider");
+            Err(OperationError::KP0060KeyObjectNoActiveSaml2Signing)
+        }
+    }
+
+    fn kerberos_assert(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError> {
+        let koi = self
+            .kerberos
+            .get_or_insert_with(KeyObjectInternalKerberos::default);
+
+        koi.assert_active(valid_from, cid)
+    }
+
+    fn kerberos_service_keys(
+        &self,
+        service: &KerberosPrincipal,
+    ) -> Result<Vec<KerberosServiceKey>, OperationError> {
+        self.kerberos
+            .as_ref()
+            .map(|kerberos| kerberos.service_keys(service))
+            .unwrap_or_else(|| Ok(Vec::with_capacity(0)))
+    }
+
+    fn kerberos_ticket_issue(
+        &self,
+        client: &KerberosPrincipal,
+        service: &KerberosPrincipal,
+        auth_time: Duration,
+        end_time: Duration,
+    ) -> Result<KerberosCredential, OperationError> {
+        if let Some(kerberos) = &self.kerberos {
+            kerberos.issue(client, service, auth_time, end_time)
+        } else {
+            error!(provider_uuid = ?self.uuid, "kerberos not available on this provider");
+            Err(OperationError::KP0063KeyObjectNoActiveKerberosKey)
+        }
+    }
+
     #[cfg(test)]
     fn kid_status(&self, key_id: &KeyId) -> Result<Option<KeyStatus>, OperationError> {
         if let Some(jws_es256_object) = &self.jws_es256 {
@@ -929,10 +2634,31 @@ impl KeyObjectT for KeyObjectInternal {
             .jws_es256
             .iter()
             .flat_map(|jws_es256| jws_es256.to_key_iter())
+            .chain(
+                self.jws_rs256
+                    .iter()
+                    .flat_map(|jws_rs256| jws_rs256.to_key_iter()),
+            )
             .chain(
                 self.jwe_a128gcm
                     .iter()
                     .flat_map(|jwe_a128gcm| jwe_a128gcm.to_key_iter()),
+            )
+            .chain(
+                self.repl_trust_anchor
+                    .iter()
+                    .flat_map(|repl_trust_anchor| repl_trust_anchor.to_key_iter()),
+            )
+            .chain(self.ssh_ca.iter().flat_map(|ssh_ca| ssh_ca.to_key_iter()))
+            .chain(
+                self.saml2_signing
+                    .iter()
+                    .flat_map(|saml2_signing| saml2_signing.to_key_iter()),
+            )
+            .chain(
+                self.kerberos
+                    .iter()
+                    .flat_map(|kerberos| kerberos.to_key_iter()),
             );
         let key_vs = ValueSetKeyInternal::from_key_iter(key_iter)? as ValueSet;
 
@@ -954,7 +2680,6 @@ impl KeyObjectT for KeyObjectInternal {
 mod tests {
     use super::*;
     use crate::server::keys::*;
-    use compact_jwt::jws::JwsBuilder;
 
     #[tokio::test]
     async fn test_key_object_internal_basic() {
@@ -1,6 +1,8 @@
 use crate::prelude::*;
 use compact_jwt::{compact::JweCompact, jwe::Jwe};
 use compact_jwt::{Jwk, Jws, JwsCompact};
+use kanidm_lib_crypto::kerberos::{KerberosCredential, KerberosPrincipal, KerberosServiceKey};
+use kanidm_lib_crypto::prelude::{PKey, Private, X509};
 use smolset::SmolSet;
 use std::collections::BTreeSet;
 use uuid::Uuid;
@@ -33,6 +35,18 @@ pub trait KeyObjectT {
 
     fn jws_public_jwk(&self, kid: &str) -> Result<Option<Jwk>, OperationError>;
 
+    /// The public keys of every jws signing key that is currently trusted, so that
+    /// relying parties can verify signatures made before a rotation.
+    fn jws_public_jwks(&self) -> Result<Vec<Jwk>, OperationError>;
+
+    fn jws_rs256_assert(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError>;
+
+    fn jws_rs256_sign(
+        &self,
+        jws: &Jws,
+        current_time: Duration,
+    ) -> Result<JwsCompact, OperationError>;
+
     fn jwe_a128gcm_assert(&mut self, valid_from: Duration, cid: &Cid)
         -> Result<(), OperationError>;
 
@@ -44,10 +58,84 @@ pub trait KeyObjectT {
 
     fn jwe_decrypt(&self, jwec: &JweCompact) -> Result<Jwe, OperationError>;
 
+    fn repl_trust_anchor_assert(
+        &mut self,
+        valid_from: Duration,
+        cid: &Cid,
+    ) -> Result<(), OperationError>;
+
+    /// The certificates of the replication trust anchors that are currently trusted.
+    fn repl_trust_anchors(&self) -> Result<Vec<X509>, OperationError>;
+
+    /// Issue a replication identity for a server from the active trust anchor.
+    fn repl_identity_issue(
+        &self,
+        server_uuid: Uuid,
+        domain_name: &str,
+        current_time: Duration,
+    ) -> Result<(PKey<Private>, X509), OperationError>;
+
+    fn ssh_ca_assert(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError>;
+
+    /// The public keys of the ssh certificate authorities that are currently trusted, in the
+    /// OpenSSH authorized keys format.
+    fn ssh_ca_public_keys(&self) -> Result<Vec<String>, OperationError>;
+
+    /// Issue an ssh user certificate for an OpenSSH format public key from the active
+    /// certificate authority. Returns the serial and the certificate.
+    fn ssh_user_certificate_issue(
+        &self,
+        public_key: &str,
+        key_id: &str,
+        principals: &[String],
+        valid_after: Duration,
+        valid_before: Duration,
+    ) -> Result<(u64, String), OperationError>;
+
+    fn saml2_signing_assert(
+        &mut self,
+        valid_from: Duration,
+        cid: &Cid,
+    ) -> Result<(), OperationError>;
+
+    /// The certificates of the saml2 signing keys that service providers should trust.
+    fn saml2_signing_certificates(&self) -> Result<Vec<X509>, OperationError>;
+
+    /// Sign data with rsa-sha256 using the active saml2 signing key. Returns the certificate
+    /// of the key that made the signature, and the signature.
+    fn saml2_sign(
+        &self,
+        data: &[u8],
+        current_time: Duration,
+    ) -> Result<(X509, Vec<u8>), OperationError>;
+
+    fn kerberos_assert(&mut self, valid_from: Duration, cid: &Cid) -> Result<(), OperationError>;
+
+    /// The keys of a kerberos service that are derived from each trusted secret, so that
+    /// tickets issued before a rotation can still be decrypted by the service.
+    fn kerberos_service_keys(
+        &self,
+        service: &KerberosPrincipal,
+    ) -> Result<Vec<KerberosServiceKey>, OperationError>;
+
+    /// Issue a kerberos ticket for a service, encrypted with the service key that is derived
+    /// from the active secret.
+    fn kerberos_ticket_issue(
+        &self,
+        client: &KerberosPrincipal,
+        service: &KerberosPrincipal,
+        auth_time: Duration,
+        end_time: Duration,
+    ) -> Result<KerberosCredential, OperationError>;
+
     fn as_valuesets(&self) -> Result<Vec<(Attribute, ValueSet)>, OperationError>;
 
     fn duplicate(&self) -> KeyObject;
 
+    /// Perform a round trip of each cryptographic operation this key object provides
+    /// to assert that the key material is usable.
+    fn self_test(&self, current_time: Duration) -> Result<(), OperationError>;
+
     fn rotate_keys(&mut self, current_time: Duration, cid: &Cid) -> Result<(), OperationError>;
 
     fn revoke_keys(
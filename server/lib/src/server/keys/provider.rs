@@ -1,6 +1,7 @@
 use crate::prelude::*;
 
 use concread::cowcell::*;
+use kanidm_proto::internal::{SelfTestCheck, SelfTestItem, SelfTestStatus};
 use uuid::Uuid;
 
 use std::collections::BTreeMap;
@@ -159,6 +160,68 @@ impl KeyProvidersTransaction for KeyProvidersReadTransaction {
     }
 }
 
+impl KeyProvidersReadTransaction {
+    /// Test each key provider, and perform a round trip of the cryptographic operations of each
+    /// key object. Successes are summarised into a single item per check.
+    pub(crate) fn self_test(&self, current_time: Duration) -> Vec<SelfTestItem> {
+        let inner = self.inner.deref();
+        let mut items = self.self_test_providers();
+
+        let mut failed = false;
+        for key_object in inner.objects.values() {
+            if let Err(err) = key_object.self_test(current_time) {
+                error!(?err, key_object_uuid = ?key_object.uuid(), "key object failed self test");
+                failed = true;
+                items.push(SelfTestItem {
+                    check: SelfTestCheck::KeyObject,
+                    status: SelfTestStatus::Fail,
+                    detail: format!("{} - {:?}", key_object.uuid(), err),
+                });
+            }
+        }
+
+        if !failed {
+            items.push(SelfTestItem {
+                check: SelfTestCheck::KeyObject,
+                status: SelfTestStatus::Pass,
+                detail: format!("{} key objects tested", inner.objects.len()),
+            });
+        }
+
+        items
+    }
+
+    /// Test that each key provider is able to perform cryptographic operations. This is
+    /// cheap enough to be used as a readiness check.
+    pub(crate) fn self_test_providers(&self) -> Vec<SelfTestItem> {
+        let inner = self.inner.deref();
+        let mut items = Vec::with_capacity(1);
+
+        let mut failed = false;
+        for provider in inner.providers.values() {
+            if let Err(err) = provider.test() {
+                error!(?err, provider = %provider, "key provider failed self test");
+                failed = true;
+                items.push(SelfTestItem {
+                    check: SelfTestCheck::KeyProvider,
+                    status: SelfTestStatus::Fail,
+                    detail: format!("{} ({}) - {:?}", provider.name(), provider.uuid(), err),
+                });
+            }
+        }
+
+        if !failed {
+            items.push(SelfTestItem {
+                check: SelfTestCheck::KeyProvider,
+                status: SelfTestStatus::Pass,
+                detail: format!("{} key providers tested", inner.providers.len()),
+            });
+        }
+
+        items
+    }
+}
+
 pub struct KeyProvidersWriteTransaction<'a> {
     inner: CowCellWriteTxn<'a, KeyProvidersInner>,
 }
@@ -1,6 +1,7 @@
 use crate::prelude::*;
 
 use crate::migration_data;
+use crate::schema::SchemaTransaction;
 use kanidm_proto::internal::{
     DomainUpgradeCheckItem as ProtoDomainUpgradeCheckItem,
     DomainUpgradeCheckReport as ProtoDomainUpgradeCheckReport,
@@ -8,6 +9,7 @@ use kanidm_proto::internal::{
 };
 
 use super::ServerPhase;
+use std::time::Instant;
 
 impl QueryServer {
     #[instrument(level = "info", name = "system_initialisation", skip_all)]
@@ -56,17 +58,24 @@ impl QueryServer {
 
             // No domain info was present, so neither was the rest of the IDM. Bring up the
             // full IDM here.
-            match domain_target_level {
-                DOMAIN_LEVEL_8 => write_txn.migrate_domain_7_to_8()?,
-                DOMAIN_LEVEL_9 => write_txn.migrate_domain_8_to_9()?,
-                DOMAIN_LEVEL_10 => write_txn.migrate_domain_9_to_10()?,
-                DOMAIN_LEVEL_11 => write_txn.migrate_domain_10_to_11()?,
+            let bootstrap = match domain_target_level {
+                DOMAIN_LEVEL_8 => QueryServerWriteTransaction::migrate_domain_7_to_8,
+                DOMAIN_LEVEL_9 => QueryServerWriteTransaction::migrate_domain_8_to_9,
+                DOMAIN_LEVEL_10 => QueryServerWriteTransaction::migrate_domain_9_to_10,
+                DOMAIN_LEVEL_11 => QueryServerWriteTransaction::migrate_domain_10_to_11,
                 _ => {
                     error!("Invalid requested domain target level for server bootstrap");
                     debug_assert!(false);
                     return Err(OperationError::MG0009InvalidTargetLevelForBootstrap);
                 }
-            }
+            };
+
+            write_txn.apply_recorded_migration(
+                "bootstrap",
+                DOMAIN_LEVEL_0,
+                domain_target_level,
+                bootstrap,
+            )?;
         } else {
             // Domain info was present, so we need to reflect that in our server
             // domain structures. If we don't do this, the in memory domain level
@@ -194,6 +203,9 @@ impl QueryServer {
             )?;
         }
 
+        // Record the migrations that were applied, now that all of the schema exists.
+        write_txn.write_migration_records()?;
+
         // We are ready to run
         write_txn.set_phase(ServerPhase::Running);
 
@@ -206,7 +218,108 @@ impl QueryServer {
     }
 }
 
+/// A migration that was applied in the current transaction. These are written to the database
+/// as migration record entries once the schema for them exists.
+pub(crate) struct MigrationRecord {
+    name: &'static str,
+    from_level: DomainVersion,
+    to_level: DomainVersion,
+    applied_at: Duration,
+    duration: Duration,
+    entries_changed: usize,
+}
+
+impl MigrationRecord {
+    fn into_entry(self) -> EntryInitNew {
+        let duration = u32::try_from(self.duration.as_millis()).unwrap_or(u32::MAX);
+        let entries_changed = u32::try_from(self.entries_changed).unwrap_or(u32::MAX);
+
+        entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::MigrationRecord.to_value()),
+            (Attribute::Uuid, Value::Uuid(Uuid::new_v4())),
+            (Attribute::MigrationName, Value::new_iutf8(self.name)),
+            (
+                Attribute::MigrationFromLevel,
+                Value::new_uint32(self.from_level)
+            ),
+            (
+                Attribute::MigrationToLevel,
+                Value::new_uint32(self.to_level)
+            ),
+            (
+                Attribute::MigrationAppliedAt,
+                Value::new_datetime_epoch(self.applied_at)
+            ),
+            (Attribute::MigrationDuration, Value::new_uint32(duration)),
+            (
+                Attribute::MigrationEntriesChanged,
+                Value::new_uint32(entries_changed)
+            )
+        )
+    }
+}
+
 impl QueryServerWriteTransaction<'_> {
+    /// Apply a migration, keeping a record of how long it took and how many entries it changed.
+    pub(crate) fn apply_recorded_migration<F>(
+        &mut self,
+        name: &'static str,
+        from_level: DomainVersion,
+        to_level: DomainVersion,
+        migration: F,
+    ) -> Result<(), OperationError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), OperationError>,
+    {
+        let start = Instant::now();
+        let changed_before = self.changed_uuid.len();
+
+        migration(self)?;
+
+        let record = MigrationRecord {
+            name,
+            from_level,
+            to_level,
+            applied_at: self.get_curtime(),
+            duration: start.elapsed(),
+            entries_changed: self.changed_uuid.len().saturating_sub(changed_before),
+        };
+        info!(
+            migration = record.name,
+            from_level = record.from_level,
+            to_level = record.to_level,
+            duration = ?record.duration,
+            entries_changed = record.entries_changed,
+            "Applied migration"
+        );
+        self.pending_migrations.push(record);
+
+        Ok(())
+    }
+
+    /// Write the records of the migrations applied in this transaction. If the schema for the
+    /// records doesn't exist yet, they are kept until a later migration has created it.
+    pub(crate) fn write_migration_records(&mut self) -> Result<(), OperationError> {
+        if self.pending_migrations.is_empty()
+            || !self
+                .schema
+                .get_classes()
+                .contains_key(EntryClass::MigrationRecord.into())
+        {
+            return Ok(());
+        }
+
+        let entries = std::mem::take(&mut self.pending_migrations)
+            .into_iter()
+            .map(MigrationRecord::into_entry)
+            .collect();
+
+        self.internal_create(entries).inspect_err(|err| {
+            error!(?err, "Unable to record applied migrations");
+        })
+    }
+
     /// Apply a domain migration `to_level`. Panics if `to_level` is not greater than the active
     /// level.
     pub(crate) fn internal_apply_domain_migration(
@@ -781,6 +894,30 @@ mod tests {
             .expect("Unable to set domain level to version 10");
 
         // post migration verification.
+        let records = write_txn
+            .internal_search(filter!(f_eq(
+                Attribute::Class,
+                EntryClass::MigrationRecord.into()
+            )))
+            .expect("Unable to search migration records");
+
+        let record = records
+            .iter()
+            .find(|record| {
+                record.get_ava_single_iutf8(Attribute::MigrationName) == Some("domain_9_to_10")
+            })
+            .expect("Migration to domain level 10 was not recorded");
+        assert_eq!(
+            record.get_ava_single_uint32(Attribute::MigrationFromLevel),
+            Some(DOMAIN_LEVEL_9)
+        );
+        assert_eq!(
+            record.get_ava_single_uint32(Attribute::MigrationToLevel),
+            Some(DOMAIN_LEVEL_10)
+        );
+        assert!(record
+            .get_ava_single_uint32(Attribute::MigrationEntriesChanged)
+            .is_some_and(|changed| changed > 0));
 
         write_txn.commit().expect("Unable to commit");
     }
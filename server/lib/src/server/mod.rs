@@ -6,17 +6,20 @@ use self::access::{
         AccessControlCreate, AccessControlDelete, AccessControlModify, AccessControlSearch,
     },
     AccessControls, AccessControlsReadTransaction, AccessControlsTransaction,
-    AccessControlsWriteTransaction,
+    AccessControlsWriteTransaction, StagedAccessControls,
 };
 use self::keys::{
     KeyObject, KeyProvider, KeyProviders, KeyProvidersReadTransaction, KeyProvidersTransaction,
     KeyProvidersWriteTransaction,
 };
 use crate::be::{Backend, BackendReadTransaction, BackendTransaction, BackendWriteTransaction};
+use crate::credential::denylist::password_denylist_normalise;
 use crate::filter::{
     Filter, FilterInvalid, FilterValid, FilterValidResolved, ResolveFilterCache,
     ResolveFilterCacheReadTxn,
 };
+use crate::idm::audit::{AuditEvent, AuditSource};
+use crate::metrics::ServerMetrics;
 use crate::plugins::dyngroup::{DynGroup, DynGroupCache};
 use crate::plugins::Plugins;
 use crate::prelude::*;
@@ -34,29 +37,41 @@ use crate::valueset::*;
 use concread::arcache::{ARCacheBuilder, ARCacheReadTxn};
 use concread::cowcell::*;
 use hashbrown::{HashMap, HashSet};
-use kanidm_proto::internal::{DomainInfo as ProtoDomainInfo, ImageValue, UiHint};
+use kanidm_proto::internal::{
+    Announcement, DomainInfo as ProtoDomainInfo, ImageValue, QueryAnalysisReport, UiHint, UiTheme,
+};
 use kanidm_proto::scim_v1::client::ScimFilter;
 use kanidm_proto::scim_v1::server::ScimOAuth2ClaimMap;
 use kanidm_proto::scim_v1::server::ScimOAuth2ScopeMap;
 use kanidm_proto::scim_v1::server::ScimReference;
 use kanidm_proto::scim_v1::JsonValue;
 use kanidm_proto::scim_v1::ScimEntryGetQuery;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::{Semaphore, SemaphorePermit};
 use tracing::trace;
 
 pub(crate) mod access;
+pub(crate) mod acme;
+pub(crate) mod admin;
+pub(crate) mod announcement;
+pub mod apply;
 pub mod batch_modify;
 pub mod create;
 pub mod delete;
+pub mod dryrun;
 pub mod identity;
+pub(crate) mod import;
 pub(crate) mod keys;
 pub(crate) mod migrations;
 pub mod modify;
 pub(crate) mod recycle;
+pub(crate) mod restore;
+pub(crate) mod selftest;
 pub mod scim;
+pub mod verify;
 
 const RESOLVE_FILTER_CACHE_MAX: usize = 256;
 const RESOLVE_FILTER_CACHE_LOCAL: usize = 8;
@@ -81,6 +96,10 @@ pub struct DomainInfo {
     pub(crate) d_devel_taint: bool,
     pub(crate) d_ldap_allow_unix_pw_bind: bool,
     pub(crate) d_allow_easter_eggs: bool,
+    d_theme: UiTheme,
+    d_login_banner: Option<String>,
+    d_primary_colour: Option<String>,
+    d_announcements: Vec<Announcement>,
     // In future this should be image reference instead of the image itself.
     d_image: Option<ImageValue>,
 }
@@ -110,6 +129,38 @@ impl DomainInfo {
         self.d_allow_easter_eggs
     }
 
+    /// The default theme of the web interface, for when a person has not chosen their own.
+    pub fn theme(&self) -> UiTheme {
+        self.d_theme
+    }
+
+    /// A message, such as a legal notice, to show on the login pages.
+    pub fn login_banner(&self) -> Option<&str> {
+        self.d_login_banner.as_deref()
+    }
+
+    /// The primary colour of the web interface, as a hex colour such as `#1f6feb`.
+    pub fn primary_colour(&self) -> Option<&str> {
+        self.d_primary_colour.as_deref()
+    }
+
+    /// The announcements that are shown at this time, most severe first.
+    pub fn announcements(&self, ct: Duration) -> Vec<&Announcement> {
+        let time = time::OffsetDateTime::UNIX_EPOCH + ct;
+        let mut announcements: Vec<_> = self
+            .d_announcements
+            .iter()
+            .filter(|announcement| announcement.is_active(time))
+            .collect();
+        announcements.sort_by(|a, b| b.severity.cmp(&a.severity));
+        announcements
+    }
+
+    /// The announcements that are shown now, for rendering in the web interface.
+    pub fn active_announcements(&self) -> Vec<&Announcement> {
+        self.announcements(duration_from_epoch_now())
+    }
+
     #[cfg(feature = "test")]
     pub fn new_test() -> CowCell<Self> {
         concread::cowcell::CowCell::new(Self {
@@ -121,6 +172,10 @@ impl DomainInfo {
             d_devel_taint: false,
             d_ldap_allow_unix_pw_bind: false,
             d_allow_easter_eggs: false,
+            d_theme: UiTheme::Auto,
+            d_login_banner: None,
+            d_primary_colour: None,
+            d_announcements: Vec::new(),
             d_image: None,
         })
     }
@@ -130,6 +185,29 @@ impl DomainInfo {
 pub struct SystemConfig {
     pub(crate) denied_names: HashSet<String>,
     pub(crate) pw_badlist: HashSet<String>,
+    /// The denied password terms as configured, and their normalised form.
+    pub(crate) pw_denylist: BTreeMap<String, String>,
+}
+
+/// How long this server keeps the history needed for replication, and recycled entries,
+/// before they are purged. These are local to each server, but the changelog retention
+/// should be the same on all servers in a topology.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    /// How long changes are kept in the changelog. A replica that has not replicated within
+    /// this window must be refreshed.
+    pub changelog_max_age: Duration,
+    /// How long recycled entries are kept before they become tombstones.
+    pub recyclebin_max_age: Duration,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            changelog_max_age: Duration::from_secs(CHANGELOG_MAX_AGE),
+            recyclebin_max_age: Duration::from_secs(RECYCLEBIN_MAX_AGE),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -137,6 +215,8 @@ pub struct QueryServer {
     phase: Arc<CowCell<ServerPhase>>,
     pub(crate) d_info: Arc<CowCell<DomainInfo>>,
     system_config: Arc<CowCell<SystemConfig>>,
+    retention: Arc<CowCell<Retention>>,
+    maintenance: Arc<CowCell<bool>>,
     be: Backend,
     schema: Arc<Schema>,
     accesscontrols: Arc<AccessControls>,
@@ -181,6 +261,7 @@ bitflags::bitflags! {
         const SYNC_AGREEMENT = 0b0010_0000;
         const KEY_MATERIAL   = 0b0100_0000;
         const APPLICATION    = 0b1000_0000;
+        const ANNOUNCEMENT   = 0b1_0000_0000;
     }
 }
 
@@ -192,6 +273,9 @@ pub struct QueryServerWriteTransaction<'a> {
     curtime: Duration,
     cid: CowCellWriteTxn<'a, Cid>,
     trim_cid: Cid,
+    retention: Retention,
+    // When set, changes requested by users are refused.
+    maintenance: bool,
     pub(crate) be_txn: BackendWriteTransaction<'a>,
     pub(crate) schema: SchemaWriteTransaction<'a>,
     accesscontrols: AccessControlsWriteTransaction<'a>,
@@ -203,6 +287,10 @@ pub struct QueryServerWriteTransaction<'a> {
 
     // Store the list of changed uuids for other invalidation needs?
     pub(super) changed_uuid: HashSet<Uuid>,
+    // Changes made by users, which the idm server audits once they are committed.
+    pub(crate) pending_activity: Vec<AuditEvent>,
+    // Migrations applied in this transaction that have not been recorded yet.
+    pub(super) pending_migrations: Vec<migrations::MigrationRecord>,
     _db_ticket: SemaphorePermit<'a>,
     _write_ticket: SemaphorePermit<'a>,
     resolve_filter_cache: ARCacheReadTxn<
@@ -218,6 +306,10 @@ impl QueryServerWriteTransaction<'_> {
     pub(crate) fn trim_cid(&self) -> &Cid {
         &self.trim_cid
     }
+
+    pub(crate) fn retention(&self) -> &Retention {
+        &self.retention
+    }
 }
 
 /// The `QueryServerTransaction` trait provides a set of common read only operations to be
@@ -242,8 +334,13 @@ pub trait QueryServerTransaction<'a> {
     type KeyProvidersTransactionType: KeyProvidersTransaction;
     fn get_key_providers(&self) -> &Self::KeyProvidersTransactionType;
 
+    /// The metrics of the backend of this query server.
+    fn get_metrics(&self) -> &ServerMetrics;
+
     fn pw_badlist(&self) -> &HashSet<String>;
 
+    fn pw_denylist(&self) -> &BTreeMap<String, String>;
+
     fn denied_names(&self) -> &HashSet<String>;
 
     fn get_domain_version(&self) -> DomainVersion;
@@ -309,6 +406,8 @@ pub trait QueryServerTransaction<'a> {
         &mut self,
         se: &SearchEvent,
     ) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError> {
+        let start = Instant::now();
+
         if se.ident.is_internal() {
             trace!(internal_filter = ?se.filter, "search");
         } else {
@@ -353,10 +452,13 @@ pub trait QueryServerTransaction<'a> {
         // attribute set on the entries!
         //
         let access = self.get_accesscontrols();
-        access.search_filter_entries(se, res).map_err(|e| {
-            admin_error!(?e, "Unable to access filter entries");
-            e
-        })
+        access
+            .search_filter_entries(se, res)
+            .map_err(|e| {
+                admin_error!(?e, "Unable to access filter entries");
+                e
+            })
+            .inspect(|_| self.get_metrics().record_search(start.elapsed()))
     }
 
     #[instrument(level = "debug", skip_all)]
@@ -724,6 +826,8 @@ pub trait QueryServerTransaction<'a> {
                     SyntaxType::Certificate => Value::new_certificate_s(value)
                         .ok_or_else(|| OperationError::InvalidAttribute("Invalid x509 certificate syntax".to_string())),
                     SyntaxType::ApplicationPassword => Err(OperationError::InvalidAttribute("ApplicationPassword values can not be supplied through modification".to_string())),
+                    SyntaxType::CredentialUsage => Err(OperationError::InvalidAttribute("CredentialUsage values are generated and not able to be set.".to_string())),
+                    SyntaxType::PublicBinary => Err(OperationError::InvalidAttribute("Public keys can not be supplied through modification - please use the IDM api".to_string())),
                 }
             }
             None => {
@@ -855,6 +959,15 @@ pub trait QueryServerTransaction<'a> {
                             )
                         })
                     }
+                    SyntaxType::CredentialUsage => {
+                        PartialValue::new_uuid_s(value).ok_or_else(|| {
+                            OperationError::InvalidAttribute(
+                                "Invalid credential usage syntax, expected credential uuid"
+                                    .to_string(),
+                            )
+                        })
+                    }
+                    SyntaxType::PublicBinary => Ok(PartialValue::new_publicbinary_tag_s(value)),
                 }
             }
             None => {
@@ -1114,6 +1227,12 @@ pub trait QueryServerTransaction<'a> {
             SyntaxType::ApplicationPassword => Err(OperationError::InvalidAttribute(
                 "Application Passwords are not able to be set.".to_string(),
             )),
+            SyntaxType::CredentialUsage => Err(OperationError::InvalidAttribute(
+                "Credential Usage is not able to be set.".to_string(),
+            )),
+            SyntaxType::PublicBinary => Err(OperationError::InvalidAttribute(
+                "Public keys are not able to be set.".to_string(),
+            )),
         }?;
 
         match resolve_status {
@@ -1323,6 +1442,14 @@ pub trait QueryServerTransaction<'a> {
         })
     }
 
+    fn get_domain_ldap_group_compat(&mut self) -> Result<bool, OperationError> {
+        self.internal_search_uuid(UUID_DOMAIN_INFO).map(|entry| {
+            entry
+                .get_ava_single_bool(Attribute::LdapGroupCompat)
+                .unwrap_or_default()
+        })
+    }
+
     /// Get the password badlist from the system config. You should not call this directly
     /// as this value is cached in the system_config() value.
     fn get_sc_password_badlist(&mut self) -> Result<HashSet<String>, OperationError> {
@@ -1340,6 +1467,27 @@ pub trait QueryServerTransaction<'a> {
             })
     }
 
+    /// Get the denied password terms from the system config. You should not call this
+    /// directly as this value is cached in the system_config() value.
+    fn get_sc_password_denylist(&mut self) -> Result<BTreeMap<String, String>, OperationError> {
+        self.internal_search_uuid(UUID_SYSTEM_CONFIG)
+            .map(
+                |e| match e.get_ava_iter_iutf8(Attribute::DeniedPasswordTerm) {
+                    Some(vs_str_iter) => vs_str_iter
+                        .map(|term| (term.to_string(), password_denylist_normalise(term)))
+                        .collect(),
+                    None => BTreeMap::default(),
+                },
+            )
+            .map_err(|e| {
+                error!(
+                    ?e,
+                    "Failed to retrieve password denylist from system configuration"
+                );
+                e
+            })
+    }
+
     /// Get the denied name set from the system config. You should not call this directly
     /// as this value is cached in the system_config() value.
     fn get_sc_denied_names(&mut self) -> Result<HashSet<String>, OperationError> {
@@ -1444,6 +1592,10 @@ impl<'a> QueryServerTransaction<'a> for QueryServerReadTransaction<'a> {
         &self.key_providers
     }
 
+    fn get_metrics(&self) -> &ServerMetrics {
+        self.be_txn.get_metrics()
+    }
+
     fn get_resolve_filter_cache(&mut self) -> &mut ResolveFilterCacheReadTxn<'a> {
         &mut self.resolve_filter_cache
     }
@@ -1461,6 +1613,10 @@ impl<'a> QueryServerTransaction<'a> for QueryServerReadTransaction<'a> {
         &self.system_config.pw_badlist
     }
 
+    fn pw_denylist(&self) -> &BTreeMap<String, String> {
+        &self.system_config.pw_denylist
+    }
+
     fn denied_names(&self) -> &HashSet<String> {
         &self.system_config.denied_names
     }
@@ -1511,6 +1667,27 @@ impl QueryServerReadTransaction<'_> {
         })
     }
 
+    /// Summarise how searches have been resolved since the server started, and recommend the
+    /// indexes that they were missing.
+    pub fn query_analysis_report(&mut self) -> Result<QueryAnalysisReport, OperationError> {
+        let be_txn = self.get_be_txn();
+        be_txn.get_query_stats().report(be_txn.get_idxmeta_ref())
+    }
+
+    /// Read the entries that most requests depend on, so that they are held in the entry cache
+    /// rather than being loaded from the database by the first requests after a restart. These
+    /// are the schema, the access controls and the groups.
+    pub fn warm_entry_cache(&mut self) -> Result<usize, OperationError> {
+        let filt = filter!(f_or!([
+            f_eq(Attribute::Class, EntryClass::AttributeType.into()),
+            f_eq(Attribute::Class, EntryClass::ClassType.into()),
+            f_eq(Attribute::Class, EntryClass::AccessControlProfile.into()),
+            f_eq(Attribute::Class, EntryClass::Group.into())
+        ]));
+
+        self.internal_search(filt).map(|entries| entries.len())
+    }
+
     /// Verify the data content of the server is as expected. This will probably
     /// call various functions for validation, including possibly plugin
     /// verifications.
@@ -1678,6 +1855,10 @@ impl<'a> QueryServerTransaction<'a> for QueryServerWriteTransaction<'a> {
         &self.key_providers
     }
 
+    fn get_metrics(&self) -> &ServerMetrics {
+        self.be_txn.get_metrics()
+    }
+
     fn get_resolve_filter_cache(&mut self) -> &mut ResolveFilterCacheReadTxn<'a> {
         &mut self.resolve_filter_cache
     }
@@ -1695,6 +1876,10 @@ impl<'a> QueryServerTransaction<'a> for QueryServerWriteTransaction<'a> {
         &self.system_config.pw_badlist
     }
 
+    fn pw_denylist(&self) -> &BTreeMap<String, String> {
+        &self.system_config.pw_denylist
+    }
+
     fn denied_names(&self) -> &HashSet<String> {
         &self.system_config.denied_names
     }
@@ -1765,6 +1950,10 @@ impl QueryServer {
             d_devel_taint: option_env!("KANIDM_PRE_RELEASE").is_some(),
             d_ldap_allow_unix_pw_bind: false,
             d_allow_easter_eggs: false,
+            d_theme: UiTheme::Auto,
+            d_login_banner: None,
+            d_primary_colour: None,
+            d_announcements: Vec::new(),
             d_image: None,
         }));
 
@@ -1774,6 +1963,10 @@ impl QueryServer {
         // These default to empty, but they'll be populated shortly.
         let system_config = Arc::new(CowCell::new(SystemConfig::default()));
 
+        let retention = Arc::new(CowCell::new(Retention::default()));
+
+        let maintenance = Arc::new(CowCell::new(false));
+
         let dyngroup_cache = Arc::new(CowCell::new(DynGroupCache::default()));
 
         let phase = Arc::new(CowCell::new(ServerPhase::Bootstrap));
@@ -1800,6 +1993,8 @@ impl QueryServer {
             phase,
             d_info,
             system_config,
+            retention,
+            maintenance,
             be,
             schema: Arc::new(schema),
             accesscontrols: Arc::new(AccessControls::default()),
@@ -1813,6 +2008,31 @@ impl QueryServer {
         })
     }
 
+    /// Set how long the changelog and recycled entries are kept. This applies from the next
+    /// transaction.
+    pub fn set_retention(&self, retention: Retention) {
+        let mut retention_write = self.retention.write();
+        *retention_write = retention;
+        retention_write.commit();
+    }
+
+    /// Enable or disable maintenance mode. While enabled the server is read only to clients -
+    /// their changes are refused, but reads, authentication and internal changes such as the
+    /// recording of sessions continue. This applies from the next transaction.
+    pub fn set_maintenance_mode(&self, enabled: bool) {
+        let mut maintenance_write = self.maintenance.write();
+        *maintenance_write = enabled;
+        maintenance_write.commit();
+    }
+
+    pub fn maintenance_mode(&self) -> bool {
+        *self.maintenance.read()
+    }
+
+    pub(crate) fn metrics(&self) -> &ServerMetrics {
+        self.be.metrics()
+    }
+
     pub fn try_quiesce(&self) {
         self.be.try_quiesce();
         self.accesscontrols.try_quiesce();
@@ -1885,7 +2105,8 @@ impl QueryServer {
         let schema = self.schema.read();
 
         let cid_max = self.cid_max.read();
-        let trim_cid = cid_max.sub_secs(CHANGELOG_MAX_AGE)?;
+        let retention = self.retention.read();
+        let trim_cid = cid_max.sub_secs(retention.changelog_max_age.as_secs())?;
 
         let be_txn = self.be.read()?;
 
@@ -1979,7 +2200,10 @@ impl QueryServer {
         // Update the cid now.
         *cid = Cid::new_lamport(cid.s_uuid, curtime, &cid.ts);
 
-        let trim_cid = cid.sub_secs(CHANGELOG_MAX_AGE)?;
+        let retention = *self.retention.read();
+        let trim_cid = cid.sub_secs(retention.changelog_max_age.as_secs())?;
+
+        let maintenance = *self.maintenance.read();
 
         Ok(QueryServerWriteTransaction {
             // I think this is *not* needed, because commit is mut self which should
@@ -1995,11 +2219,15 @@ impl QueryServer {
             curtime,
             cid,
             trim_cid,
+            retention,
+            maintenance,
             be_txn,
             schema: schema_write,
             accesscontrols: self.accesscontrols.write(),
             changed_flags: ChangeFlag::empty(),
             changed_uuid: HashSet::new(),
+            pending_activity: Vec::new(),
+            pending_migrations: Vec::new(),
             _db_ticket: db_ticket,
             _write_ticket: write_ticket,
             resolve_filter_cache: self.resolve_filter_cache.read(),
@@ -2047,10 +2275,41 @@ impl<'a> QueryServerWriteTransaction<'a> {
         self.curtime
     }
 
+    /// Refuse changes that were requested by a user while the server is in maintenance mode.
+    /// Internal changes are always allowed so that authentication and background tasks can
+    /// continue.
+    pub(crate) fn check_maintenance_mode(&self, ident: &Identity) -> Result<(), OperationError> {
+        if ident.is_internal() {
+            Ok(())
+        } else {
+            self.refuse_in_maintenance_mode()
+        }
+    }
+
+    /// Some operations are requested by a user, but are applied with internal changes. These
+    /// must check for maintenance mode themselves.
+    pub(crate) fn refuse_in_maintenance_mode(&self) -> Result<(), OperationError> {
+        if self.maintenance {
+            security_info!("refusing change, server is in maintenance mode");
+            Err(OperationError::KG008MaintenanceModeReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+
     pub(crate) fn get_cid(&self) -> &Cid {
         &self.cid
     }
 
+    /// Advance the change clock of this server past a change that was observed from another
+    /// server. This keeps changes made on this server after that point ordered after it, even
+    /// if the clock of the other server is ahead of ours.
+    pub(crate) fn advance_cid(&mut self, observed_ts: Duration) {
+        let s_uuid = self.cid.s_uuid;
+        let ts = self.cid.ts;
+        *self.cid = Cid::new_lamport(s_uuid, ts, &observed_ts);
+    }
+
     pub(crate) fn get_key_providers_mut(&mut self) -> &mut KeyProvidersWriteTransaction<'a> {
         &mut self.key_providers
     }
@@ -2312,10 +2571,71 @@ impl<'a> QueryServerWriteTransaction<'a> {
             e
         })?;
 
-        self.accesscontrols.update_delete(delete_acps).map_err(|e| {
-            admin_error!("Failed to update delete accesscontrols {:?}", e);
+        self.accesscontrols
+            .update_delete(delete_acps)
+            .map_err(|e| {
+                admin_error!("Failed to update delete accesscontrols {:?}", e);
+                e
+            })?;
+
+        self.reload_staged_accesscontrols()
+    }
+
+    /// Staged access controls are the access controls that would be enforced if each staged
+    /// change of `acp_enable` were applied. They are evaluated alongside the enforced ones so
+    /// that the impact of the change can be observed, but they are never enforced.
+    fn reload_staged_accesscontrols(&mut self) -> Result<(), OperationError> {
+        let filt = filter!(f_eq(
+            Attribute::Class,
+            EntryClass::AccessControlProfile.into()
+        ));
+
+        let res = self.internal_search(filt).map_err(|e| {
+            admin_error!(?e, "reload staged accesscontrols internal search failed");
             e
-        })
+        })?;
+
+        let is_staged = |e: &Arc<EntrySealedCommitted>| {
+            e.get_ava_single_bool(Attribute::AcpStaged).unwrap_or(false)
+        };
+
+        if !res.iter().any(is_staged) {
+            self.accesscontrols.update_staged(None);
+            return Ok(());
+        }
+
+        let mut staged = StagedAccessControls::default();
+
+        // A profile is in the staged set when it is enabled and not staged, or when it is
+        // disabled and staged to be enabled.
+        for e in res
+            .iter()
+            .filter(|e| e.get_ava_single_bool(Attribute::AcpEnable).unwrap_or(true) != is_staged(e))
+        {
+            if e.attribute_equality(Attribute::Class, &EntryClass::AccessControlSearch.into()) {
+                staged
+                    .acps_search
+                    .push(AccessControlSearch::try_from(self, e)?);
+            }
+            if e.attribute_equality(Attribute::Class, &EntryClass::AccessControlCreate.into()) {
+                staged
+                    .acps_create
+                    .push(AccessControlCreate::try_from(self, e)?);
+            }
+            if e.attribute_equality(Attribute::Class, &EntryClass::AccessControlModify.into()) {
+                staged
+                    .acps_modify
+                    .push(AccessControlModify::try_from(self, e)?);
+            }
+            if e.attribute_equality(Attribute::Class, &EntryClass::AccessControlDelete.into()) {
+                staged
+                    .acps_delete
+                    .push(AccessControlDelete::try_from(self, e)?);
+            }
+        }
+
+        self.accesscontrols.update_staged(Some(staged));
+        Ok(())
     }
 
     #[instrument(level = "debug", skip_all)]
@@ -2357,10 +2677,12 @@ impl<'a> QueryServerWriteTransaction<'a> {
     pub(crate) fn reload_system_config(&mut self) -> Result<(), OperationError> {
         let denied_names = self.get_sc_denied_names()?;
         let pw_badlist = self.get_sc_password_badlist()?;
+        let pw_denylist = self.get_sc_password_denylist()?;
 
         let mut_system_config = self.system_config.get_mut();
         mut_system_config.denied_names = denied_names;
         mut_system_config.pw_badlist = pw_badlist;
+        mut_system_config.pw_denylist = pw_denylist;
         Ok(())
     }
 
@@ -2440,26 +2762,48 @@ impl<'a> QueryServerWriteTransaction<'a> {
 
         if previous_version <= DOMAIN_LEVEL_8 && domain_info_version >= DOMAIN_LEVEL_9 {
             // 1.4 -> 1.5
-            self.migrate_domain_8_to_9()?;
+            self.apply_recorded_migration(
+                "domain_8_to_9",
+                previous_version,
+                DOMAIN_LEVEL_9,
+                Self::migrate_domain_8_to_9,
+            )?;
         }
 
         if previous_patch_level < PATCH_LEVEL_2
             && domain_info_patch_level >= PATCH_LEVEL_2
             && domain_info_version == DOMAIN_LEVEL_9
         {
-            self.migrate_domain_patch_level_2()?;
+            self.apply_recorded_migration(
+                "domain_patch_level_2",
+                DOMAIN_LEVEL_9,
+                DOMAIN_LEVEL_9,
+                Self::migrate_domain_patch_level_2,
+            )?;
         }
 
         if previous_version <= DOMAIN_LEVEL_9 && domain_info_version >= DOMAIN_LEVEL_10 {
             // 1.5 -> 1.6
-            self.migrate_domain_9_to_10()?;
+            self.apply_recorded_migration(
+                "domain_9_to_10",
+                previous_version.max(DOMAIN_LEVEL_9),
+                DOMAIN_LEVEL_10,
+                Self::migrate_domain_9_to_10,
+            )?;
         }
 
         if previous_version <= DOMAIN_LEVEL_10 && domain_info_version >= DOMAIN_LEVEL_11 {
             // 1.6 -> 1.7
-            self.migrate_domain_10_to_11()?;
+            self.apply_recorded_migration(
+                "domain_10_to_11",
+                previous_version.max(DOMAIN_LEVEL_10),
+                DOMAIN_LEVEL_11,
+                Self::migrate_domain_10_to_11,
+            )?;
         }
 
+        self.write_migration_records()?;
+
         // This is here to catch when we increase domain levels but didn't create the migration
         // hooks. If this fails it probably means you need to add another migration hook
         // in the above.
@@ -2489,6 +2833,24 @@ impl<'a> QueryServerWriteTransaction<'a> {
 
         let domain_image = domain_entry.get_ava_single_image(Attribute::Image);
 
+        let domain_theme = domain_entry
+            .get_ava_single_iutf8(Attribute::DomainTheme)
+            .and_then(|theme| {
+                UiTheme::from_str(theme)
+                    .map_err(|_| warn!(?theme, "Ignoring invalid domain theme"))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        let domain_login_banner = domain_entry
+            .get_ava_single_utf8(Attribute::DomainLoginBanner)
+            .map(str::to_string);
+
+        // This is validated by the domain plugin, so it's safe to render into a stylesheet.
+        let domain_primary_colour = domain_entry
+            .get_ava_single_iutf8(Attribute::DomainPrimaryColour)
+            .map(str::to_string);
+
         let domain_uuid = self.be_txn.get_db_d_uuid()?;
 
         let mut_d_info = self.d_info.get_mut();
@@ -2514,6 +2876,9 @@ impl<'a> QueryServerWriteTransaction<'a> {
         }
         mut_d_info.d_display = display_name;
         mut_d_info.d_image = domain_image;
+        mut_d_info.d_theme = domain_theme;
+        mut_d_info.d_login_banner = domain_login_banner;
+        mut_d_info.d_primary_colour = domain_primary_colour;
         Ok(())
     }
 
@@ -2557,6 +2922,33 @@ impl<'a> QueryServerWriteTransaction<'a> {
         self.be_txn.reindex(immediate)
     }
 
+    /// The attributes that have indexes, in the order that an online reindex visits them.
+    pub fn get_indexed_attributes(&self) -> Vec<Attribute> {
+        let mut attrs: Vec<Attribute> = self
+            .be_txn
+            .get_idxmeta_ref()
+            .idxkeys
+            .keys()
+            .map(|ikey| ikey.attr.clone())
+            .collect();
+        attrs.sort_unstable();
+        attrs.dedup();
+        attrs
+    }
+
+    /// Rebuild the indexes of one attribute as part of an online reindex.
+    pub fn reindex_attribute(&mut self, attr: &Attribute) -> Result<(), OperationError> {
+        self.be_txn.reindex_attribute(attr)
+    }
+
+    /// Complete an online reindex once every attribute has been rebuilt. The index slopes are
+    /// recalculated, and the schema reloaded so that queries use them.
+    pub fn reindex_complete(&mut self) -> Result<(), OperationError> {
+        self.be_txn.reindex_analyse()?;
+        self.force_schema_reload();
+        Ok(())
+    }
+
     fn force_schema_reload(&mut self) {
         self.changed_flags.insert(ChangeFlag::SCHEMA);
     }
@@ -2584,6 +2976,19 @@ impl<'a> QueryServerWriteTransaction<'a> {
         self.changed_flags.remove(ChangeFlag::OAUTH2)
     }
 
+    /// Record a change made by a user or sync account so that it can be audited. Internal
+    /// changes are not recorded.
+    pub(super) fn queue_activity<F>(&mut self, ident: &Identity, event: F)
+    where
+        F: FnOnce(AuditSource, Uuid, time::OffsetDateTime) -> AuditEvent,
+    {
+        if let Some(actor) = ident.get_uuid() {
+            let time = time::OffsetDateTime::UNIX_EPOCH + self.curtime;
+            self.pending_activity
+                .push(event(ident.source().clone().into(), actor, time));
+        }
+    }
+
     fn set_phase(&mut self, phase: ServerPhase) {
         // Phase changes are one way
         if phase > *self.phase {
@@ -2654,6 +3059,14 @@ impl<'a> QueryServerWriteTransaction<'a> {
             self.reload_domain_info()?;
         }
 
+        // Announcements are also loaded with the domain info at start up.
+        if self
+            .changed_flags
+            .intersects(ChangeFlag::DOMAIN | ChangeFlag::ANNOUNCEMENT)
+        {
+            self.reload_announcements()?;
+        }
+
         // Clear flags
         self.changed_flags.remove(
             ChangeFlag::DOMAIN
@@ -2661,7 +3074,8 @@ impl<'a> QueryServerWriteTransaction<'a> {
                 | ChangeFlag::SYSTEM_CONFIG
                 | ChangeFlag::ACP
                 | ChangeFlag::SYNC_AGREEMENT
-                | ChangeFlag::KEY_MATERIAL,
+                | ChangeFlag::KEY_MATERIAL
+                | ChangeFlag::ANNOUNCEMENT,
         );
 
         Ok(())
@@ -2695,8 +3109,12 @@ impl<'a> QueryServerWriteTransaction<'a> {
             // Ignore values that don't need a commit.
             curtime: _,
             trim_cid: _,
+            retention: _,
+            maintenance: _,
             changed_flags,
             changed_uuid: _,
+            pending_activity: _,
+            pending_migrations: _,
             resolve_filter_cache: _,
         } = self;
         debug_assert!(!committed);
@@ -2733,6 +3151,7 @@ impl<'a> QueryServerWriteTransaction<'a> {
 #[cfg(test)]
 mod tests {
     use crate::prelude::*;
+    use kanidm_proto::internal::UiTheme;
     use kanidm_proto::scim_v1::client::ScimFilter;
     use kanidm_proto::scim_v1::server::ScimReference;
     use kanidm_proto::scim_v1::JsonValue;
@@ -2775,6 +3194,23 @@ mod tests {
         assert_eq!(r6, Ok(t_uuid));
     }
 
+    #[qs_test]
+    async fn test_domain_theme(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert_eq!(server_txn.d_info.theme(), UiTheme::Auto);
+
+        assert!(server_txn
+            .internal_modify_uuid(
+                UUID_DOMAIN_INFO,
+                &ModifyList::new_purge_and_set(Attribute::DomainTheme, Value::new_iutf8("dark")),
+            )
+            .is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let server_txn = server.read().await.unwrap();
+        assert_eq!(server_txn.d_info.theme(), UiTheme::Dark);
+    }
+
     #[qs_test]
     async fn test_external_id_to_uuid(server: &QueryServer) {
         let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
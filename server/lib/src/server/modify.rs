@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use super::ChangeFlag;
+use crate::idm::audit::AuditEvent;
 use crate::plugins::Plugins;
 use crate::prelude::*;
 
@@ -39,6 +40,11 @@ impl QueryServerWriteTransaction<'_> {
             security_info!(name = %me.ident, "modify initiator");
         }
 
+        // Ending a session is allowed in maintenance mode, you should always be able to logout!
+        if !me.modlist.only_revokes_sessions() {
+            self.check_maintenance_mode(&me.ident)?;
+        }
+
         // Validate input.
 
         // Is the modlist non zero?
@@ -222,6 +228,15 @@ impl QueryServerWriteTransaction<'_> {
             self.changed_flags.insert(ChangeFlag::APPLICATION)
         }
 
+        if !self.changed_flags.contains(ChangeFlag::ANNOUNCEMENT)
+            && norm_cand
+                .iter()
+                .chain(pre_candidates.iter().map(|e| e.as_ref()))
+                .any(|e| e.attribute_equality(Attribute::Class, &EntryClass::Announcement.into()))
+        {
+            self.changed_flags.insert(ChangeFlag::ANNOUNCEMENT)
+        }
+
         if !self.changed_flags.contains(ChangeFlag::OAUTH2)
             && norm_cand
                 .iter()
@@ -295,6 +310,16 @@ impl QueryServerWriteTransaction<'_> {
             changed = ?self.changed_flags.iter_names().collect::<Vec<_>>(),
         );
 
+        self.queue_activity(&me.ident, |source, actor, time| {
+            AuditEvent::EntriesModified {
+                source,
+                actor,
+                targets: norm_cand.iter().map(|e| e.get_uuid()).collect(),
+                attrs: me.modlist.iter().map(|m| m.attr().clone()).collect(),
+                time,
+            }
+        });
+
         // return
         if me.ident.is_internal() {
             trace!("Modify operation success");
@@ -433,6 +458,15 @@ impl QueryServerWriteTransaction<'_> {
             self.changed_flags.insert(ChangeFlag::APPLICATION)
         }
 
+        if !self.changed_flags.contains(ChangeFlag::ANNOUNCEMENT)
+            && norm_cand
+                .iter()
+                .chain(pre_candidates.iter().map(|e| e.as_ref()))
+                .any(|e| e.attribute_equality(Attribute::Class, &EntryClass::Announcement.into()))
+        {
+            self.changed_flags.insert(ChangeFlag::ANNOUNCEMENT)
+        }
+
         if !self.changed_flags.contains(ChangeFlag::OAUTH2)
             && norm_cand.iter().any(|e| {
                 e.attribute_equality(Attribute::Class, &EntryClass::OAuth2ResourceServer.into())
@@ -952,4 +986,72 @@ mod tests {
             Err(OperationError::AccessDenied)
         );
     }
+
+    #[qs_test]
+    async fn test_modify_maintenance_mode(server: &QueryServer) {
+        let user_uuid = uuid!("b1a2e0a4-0cb4-4b4e-8e8f-6f1b1b43a0f5");
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(user_uuid)),
+            (Attribute::Description, Value::new_utf8s("testperson1")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+        );
+
+        let rename = |ident: Identity| {
+            ModifyEvent::new_impersonate_identity(
+                ident,
+                filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(user_uuid),)),
+                ModifyList::new_list(vec![
+                    Modify::Purged(Attribute::DisplayName),
+                    Modify::Present(
+                        Attribute::DisplayName,
+                        Value::Utf8("test_person_renamed".into()),
+                    ),
+                ]),
+            )
+        };
+
+        server.set_maintenance_mode(true);
+        assert!(server.maintenance_mode());
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        // Internal changes continue.
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+
+        let testperson_entry = server_txn.internal_search_uuid(user_uuid).unwrap();
+        let user_ident = Identity::from_impersonate_entry_readwrite(testperson_entry);
+
+        // Changes from users are refused.
+        assert_eq!(
+            server_txn.modify(&rename(user_ident.clone())),
+            Err(OperationError::KG008MaintenanceModeReadOnly)
+        );
+
+        // But they may still logout.
+        let me_logout = ModifyEvent::new_impersonate_identity(
+            user_ident.clone(),
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(user_uuid),)),
+            ModifyList::new_list(vec![Modify::Removed(
+                Attribute::UserAuthTokenSession,
+                PartialValue::Refer(Uuid::new_v4()),
+            )]),
+        );
+        assert_ne!(
+            server_txn.modify(&me_logout),
+            Err(OperationError::KG008MaintenanceModeReadOnly)
+        );
+
+        assert!(server_txn.commit().is_ok());
+
+        // Once disabled, changes are accepted again.
+        server.set_maintenance_mode(false);
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn.modify(&rename(user_ident)).is_ok());
+        assert!(server_txn.commit().is_ok());
+    }
 }
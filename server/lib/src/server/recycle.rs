@@ -1,4 +1,5 @@
 use super::modify::ModifyPartial;
+use crate::be::DbCompaction;
 use crate::event::ReviveRecycledEvent;
 use crate::prelude::*;
 use crate::server::Plugins;
@@ -18,8 +19,28 @@ impl QueryServerWriteTransaction<'_> {
                 error!(err = ?e, "Tombstone purge operation failed (backend)");
                 e
             })
-            .inspect(|_res| {
-                admin_info!("Tombstone purge operation success");
+            .inspect(|reaped| {
+                self.get_metrics().record_tombstones_reaped(*reaped);
+                admin_info!(reaped, "Tombstone purge operation success");
+            })
+    }
+
+    /// Return the space freed by purges to the filesystem where the database allows it.
+    #[instrument(level = "debug", skip_all)]
+    pub fn compact(&mut self) -> Result<DbCompaction, OperationError> {
+        self.be_txn
+            .compact()
+            .inspect_err(|err| {
+                error!(?err, "Compaction operation failed (backend)");
+            })
+            .inspect(|compaction| {
+                self.get_metrics()
+                    .record_compaction(compaction.reclaimed, compaction.free);
+                admin_info!(
+                    reclaimed_bytes = compaction.reclaimed,
+                    free_bytes = compaction.free,
+                    "Compaction operation success"
+                );
             })
     }
 
@@ -27,7 +48,8 @@ impl QueryServerWriteTransaction<'_> {
     pub fn purge_recycled(&mut self) -> Result<usize, OperationError> {
         // Send everything that is recycled to tombstone
         // Search all recycled
-        let cid = self.cid.sub_secs(RECYCLEBIN_MAX_AGE).map_err(|e| {
+        let recyclebin_max_age = self.retention().recyclebin_max_age.as_secs();
+        let cid = self.cid.sub_secs(recyclebin_max_age).map_err(|e| {
             admin_error!(err = ?e, "Unable to generate search cid for purge_recycled");
             e
         })?;
@@ -69,7 +91,8 @@ impl QueryServerWriteTransaction<'_> {
                 e
             })
             .map(|_| {
-                admin_info!("Purge recycled operation success");
+                self.get_metrics().record_recycled_purged(touched);
+                admin_info!(touched, "Purge recycled operation success");
                 touched
             })
     }
@@ -85,6 +108,8 @@ impl QueryServerWriteTransaction<'_> {
             security_info!(name = %re.ident, "revive initiator");
         }
 
+        self.check_maintenance_mode(&re.ident)?;
+
         // Get the list of pre_candidates, using impersonate search.
         let pre_candidates =
             self.impersonate_search_valid(re.filter.clone(), re.filter.clone(), &re.ident)?;
@@ -226,7 +251,6 @@ impl QueryServerWriteTransaction<'_> {
         Ok(())
     }
 
-    #[cfg(test)]
     pub(crate) fn internal_revive_uuid(&mut self, target_uuid: Uuid) -> Result<(), OperationError> {
         // Note the use of filter_rec here for only recycled targets.
         let filter = filter_rec!(f_eq(Attribute::Uuid, PartialValue::Uuid(target_uuid)));
@@ -244,6 +268,7 @@ mod tests {
 
     use crate::event::{CreateEvent, DeleteEvent};
     use crate::server::ModifyEvent;
+    use crate::server::Retention;
     use crate::server::SearchEvent;
 
     use super::ReviveRecycledEvent;
@@ -616,6 +641,50 @@ mod tests {
         assert!(server_txn.commit().is_ok());
     }
 
+    #[qs_test]
+    async fn test_tombstone_configured_retention(server: &QueryServer) {
+        // Shorter than the defaults, so these can only be purged if the retention applies.
+        server.set_retention(Retention {
+            changelog_max_age: Duration::from_secs(120),
+            recyclebin_max_age: Duration::from_secs(60),
+        });
+
+        let time_p1 = duration_from_epoch_now();
+        let time_p2 = time_p1 + Duration::from_secs(90);
+        let time_p3 = time_p2 + Duration::from_secs(240);
+
+        let filt_i_ts = filter_all!(f_eq(Attribute::Class, EntryClass::Tombstone.into()));
+
+        let mut server_txn = server.write(time_p1).await.unwrap();
+        let ce = CreateEvent::new_internal(vec![create_user(
+            "testperson1",
+            "9557f49c-97a5-4277-a9a5-097d17eb8317",
+        )]);
+        assert!(server_txn.create(&ce).is_ok());
+
+        let de_sin = DeleteEvent::new_internal_invalid(filter!(f_eq(
+            Attribute::Name,
+            PartialValue::new_iname("testperson1")
+        )));
+        assert!(server_txn.delete(&de_sin).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.write(time_p2).await.unwrap();
+        assert_eq!(server_txn.purge_recycled(), Ok(1));
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.write(time_p3).await.unwrap();
+        assert!(server_txn.purge_tombstones().is_ok());
+        assert!(server_txn.compact().is_ok());
+
+        let r1 = server_txn
+            .internal_search(filt_i_ts)
+            .expect("internal search failed");
+        assert!(r1.is_empty());
+
+        assert!(server_txn.commit().is_ok());
+    }
+
     fn create_user(name: &str, uuid: &str) -> Entry<EntryInit, EntryNew> {
         entry_init!(
             (Attribute::Class, EntryClass::Object.to_value()),
@@ -0,0 +1,282 @@
+//! Selective restore of entries from a backup. Rather than replacing the content of the whole
+//! database, a single entry is reverted to the content it had in the backup. Any entries it
+//! references that have since been deleted are restored alongside it so that referential
+//! integrity is maintained.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::be::restore::backup_entries;
+use crate::prelude::*;
+
+enum RestoreState {
+    Live,
+    Recycled,
+    Absent,
+}
+
+/// Attributes that are maintained by the server, and will be regenerated as the restored
+/// entries are written.
+fn restore_excluded(attr: &Attribute) -> bool {
+    matches!(
+        attr,
+        Attribute::MemberOf
+            | Attribute::DirectMemberOf
+            | Attribute::Spn
+            | Attribute::LastModifiedCid
+            | Attribute::CreatedAtCid
+    )
+}
+
+fn restorable_values<F>(
+    entry: &EntrySealedCommitted,
+    resolvable: F,
+) -> BTreeMap<Attribute, Vec<Value>>
+where
+    F: Fn(Uuid) -> bool,
+{
+    entry
+        .get_ava_iter()
+        .filter(|(attr, _)| !restore_excluded(attr))
+        .map(|(attr, vs)| {
+            let values: Vec<Value> = vs
+                .to_value_iter()
+                .filter(|v| v.to_ref_uuid().map(&resolvable).unwrap_or(true))
+                .collect();
+            (attr.clone(), values)
+        })
+        .filter(|(_, values)| !values.is_empty())
+        .collect()
+}
+
+impl QueryServerWriteTransaction<'_> {
+    /// Restore the entry named by `target` (a uuid or name) to the content it has in the
+    /// backup. Referenced entries that are no longer live are also restored if they exist in
+    /// the backup. Returns the uuids of all entries that were restored.
+    #[instrument(level = "debug", skip_all)]
+    pub fn restore_entry_from_backup(
+        &mut self,
+        serialized_string: &str,
+        target: &str,
+    ) -> Result<Vec<Uuid>, OperationError> {
+        let mut backup: BTreeMap<Uuid, EntrySealedCommitted> = BTreeMap::new();
+        for (id, mut db_e) in backup_entries(serialized_string)?.into_iter().enumerate() {
+            self.be_txn.unseal_dbentry(&mut db_e)?;
+            let entry = EntrySealedCommitted::from_dbentry(db_e, id as u64).ok_or_else(|| {
+                error!("Unable to load entry from backup");
+                OperationError::InvalidDbState
+            })?;
+            // Only entries that were live at the time of the backup can be restored.
+            if entry.mask_recycled_ts().is_some() {
+                backup.insert(entry.get_uuid(), entry);
+            }
+        }
+
+        let target_uuid = Uuid::parse_str(target)
+            .ok()
+            .filter(|u| backup.contains_key(u))
+            .or_else(|| {
+                let pv = PartialValue::new_iname(target);
+                backup
+                    .values()
+                    .find(|e| e.attribute_equality(Attribute::Name, &pv))
+                    .map(|e| e.get_uuid())
+            })
+            .ok_or_else(|| {
+                error!(?target, "Unable to find entry in backup");
+                OperationError::DB0006RestoreEntryNotFound
+            })?;
+
+        // Work out what needs to be restored, following references from the target.
+        let mut restore: BTreeMap<Uuid, RestoreState> = BTreeMap::new();
+        let mut existing: BTreeSet<Uuid> = BTreeSet::new();
+        let mut unresolved: BTreeSet<Uuid> = BTreeSet::new();
+        let mut pending = vec![target_uuid];
+
+        while let Some(u) = pending.pop() {
+            if restore.contains_key(&u) || existing.contains(&u) || unresolved.contains(&u) {
+                continue;
+            }
+
+            let current = match self.internal_search_all_uuid(u) {
+                Ok(e) => Some(e),
+                Err(OperationError::NoMatchingEntries) => None,
+                Err(err) => return Err(err),
+            };
+
+            let state = match current {
+                Some(e) if e.mask_tombstone().is_none() => {
+                    if u == target_uuid {
+                        error!(?u, "Unable to restore entry, it has been tombstoned");
+                        return Err(OperationError::DB0007RestoreEntryTombstoned);
+                    }
+                    unresolved.insert(u);
+                    continue;
+                }
+                Some(e) if e.mask_recycled().is_none() => RestoreState::Recycled,
+                Some(_) if u == target_uuid => RestoreState::Live,
+                Some(_) => {
+                    // Live references are left as they are.
+                    existing.insert(u);
+                    continue;
+                }
+                None => RestoreState::Absent,
+            };
+
+            let Some(backup_entry) = backup.get(&u) else {
+                unresolved.insert(u);
+                continue;
+            };
+
+            pending.extend(
+                backup_entry
+                    .get_ava_iter()
+                    .filter(|(attr, _)| !restore_excluded(attr))
+                    .filter_map(|(_, vs)| vs.as_ref_uuid_iter())
+                    .flatten(),
+            );
+            restore.insert(u, state);
+        }
+
+        for u in unresolved.iter() {
+            warn!(
+                ?u,
+                "Referenced entry can not be restored, references to it will be removed"
+            );
+        }
+
+        let resolvable = |u: Uuid| restore.contains_key(&u) || existing.contains(&u);
+
+        for (u, _) in restore
+            .iter()
+            .filter(|(_, state)| matches!(state, RestoreState::Recycled))
+        {
+            self.internal_revive_uuid(*u)?;
+        }
+
+        let mut creates = Vec::new();
+        let mut modifies = Vec::new();
+        for (u, state) in restore.iter() {
+            let values = backup
+                .get(u)
+                .map(|e| restorable_values(e, &resolvable))
+                .ok_or(OperationError::InvalidState)?;
+
+            match state {
+                RestoreState::Absent => {
+                    let mut entry = Entry::new();
+                    for (attr, vals) in values {
+                        entry.set_ava(attr, vals);
+                    }
+                    creates.push(entry);
+                }
+                RestoreState::Live | RestoreState::Recycled => modifies.push((*u, values)),
+            }
+        }
+
+        if !creates.is_empty() {
+            self.internal_create(creates)?;
+        }
+
+        for (u, values) in modifies {
+            let current = self.internal_search_uuid(u)?;
+
+            // Remove anything that was added since the backup was taken.
+            let mut mods: Vec<Modify> = current
+                .get_ava_iter()
+                .map(|(attr, _)| attr)
+                .filter(|attr| {
+                    **attr != Attribute::Uuid
+                        && !restore_excluded(attr)
+                        && !values.contains_key(attr)
+                })
+                .map(|attr| Modify::Purged(attr.clone()))
+                .collect();
+
+            for (attr, vals) in values {
+                if attr == Attribute::Uuid {
+                    continue;
+                }
+                mods.push(Modify::Purged(attr.clone()));
+                mods.extend(vals.into_iter().map(|v| Modify::Present(attr.clone(), v)));
+            }
+
+            self.internal_modify_uuid(u, &ModifyList::new_list(mods))?;
+        }
+
+        info!(
+            ?target_uuid,
+            "Restored {} entries from backup",
+            restore.len()
+        );
+
+        Ok(restore.into_keys().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::be::BackendTransaction;
+    use crate::prelude::*;
+
+    #[qs_test]
+    async fn test_restore_entry_from_backup(server: &QueryServer) {
+        let curtime = duration_from_epoch_now();
+        let mut server_txn = server.write(curtime).await.expect("txn");
+
+        let group_uuid = Uuid::new_v4();
+        let person_uuid = Uuid::new_v4();
+
+        let e_person = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(person_uuid)),
+            (Attribute::Description, Value::new_utf8s("original")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson"))
+        );
+        let e_group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Uuid, Value::Uuid(group_uuid)),
+            (Attribute::Member, Value::Refer(person_uuid))
+        );
+        assert!(server_txn.internal_create(vec![e_person, e_group]).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let backup = {
+            let mut server_txn = server.read().await.expect("txn");
+            server_txn.get_be_txn().backup_data().expect("backup")
+        };
+
+        // Change the group, and delete the person it references.
+        let mut server_txn = server.write(curtime).await.expect("txn");
+        assert!(server_txn
+            .internal_modify_uuid(
+                group_uuid,
+                &ModifyList::new_purge_and_set(Attribute::Description, Value::new_utf8s("changed"))
+            )
+            .is_ok());
+        assert!(server_txn.internal_delete_uuid(person_uuid).is_ok());
+
+        let restored = server_txn
+            .restore_entry_from_backup(&backup, "testgroup")
+            .expect("restore failed");
+        assert!(restored.contains(&group_uuid));
+        assert!(restored.contains(&person_uuid));
+
+        let group = server_txn.internal_search_uuid(group_uuid).expect("group");
+        assert!(!group.attribute_pres(Attribute::Description));
+        assert!(group.attribute_equality(Attribute::Member, &PartialValue::Refer(person_uuid)));
+
+        let person = server_txn
+            .internal_search_uuid(person_uuid)
+            .expect("person");
+        assert!(
+            person.attribute_equality(Attribute::Description, &PartialValue::new_utf8s("original"))
+        );
+
+        assert!(server_txn.commit().is_ok());
+    }
+}
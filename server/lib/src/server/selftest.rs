@@ -0,0 +1,128 @@
+use crate::prelude::*;
+
+use crate::be::BackendTransaction;
+use crate::repl::ruv::ReplicationUpdateVectorTransaction;
+use kanidm_proto::internal::{SelfTestCheck, SelfTestItem, SelfTestStatus};
+
+impl QueryServerReadTransaction<'_> {
+    /// Assert that the cryptographic material and the clock of this server are sane.
+    pub fn self_test(
+        &mut self,
+        current_time: Duration,
+        max_clock_skew: Duration,
+    ) -> Result<Vec<SelfTestItem>, OperationError> {
+        let mut items = self.get_key_providers().self_test(current_time);
+
+        items.push(self.self_test_clock_skew(current_time, max_clock_skew)?);
+
+        Ok(items)
+    }
+
+    /// A lightweight check that the database can be read and that the key providers are
+    /// functional, suitable for being polled frequently by a load balancer.
+    pub fn readiness_check(&mut self) -> Vec<SelfTestItem> {
+        let database = match self.internal_search_uuid(UUID_DOMAIN_INFO) {
+            Ok(_) => SelfTestItem {
+                check: SelfTestCheck::Database,
+                status: SelfTestStatus::Pass,
+                detail: "database is readable".to_string(),
+            },
+            Err(err) => {
+                error!(?err, "unable to read domain info during readiness check");
+                SelfTestItem {
+                    check: SelfTestCheck::Database,
+                    status: SelfTestStatus::Fail,
+                    detail: format!("unable to read domain info - {:?}", err),
+                }
+            }
+        };
+
+        let mut items = vec![database];
+        items.extend(self.get_key_providers().self_test_providers());
+        items
+    }
+
+    /// Every server in the RUV records the time of the latest change it made. If any of these
+    /// are ahead of our clock, either our clock has gone backwards, or the clock of that
+    /// replication partner is ahead. In both cases the changes made by that server will
+    /// incorrectly win conflicts.
+    fn self_test_clock_skew(
+        &mut self,
+        current_time: Duration,
+        max_clock_skew: Duration,
+    ) -> Result<SelfTestItem, OperationError> {
+        let ruv_range = self.get_be_txn().get_ruv().current_ruv_range()?;
+
+        let max_allowed = current_time + max_clock_skew;
+
+        let skewed: Vec<_> = ruv_range
+            .iter()
+            .filter(|(_, range)| range.ts_max > max_allowed)
+            .map(|(s_uuid, range)| {
+                format!("{} +{}s", s_uuid, (range.ts_max - current_time).as_secs())
+            })
+            .collect();
+
+        if skewed.is_empty() {
+            Ok(SelfTestItem {
+                check: SelfTestCheck::ClockSkew,
+                status: SelfTestStatus::Pass,
+                detail: format!(
+                    "{} servers within {}s",
+                    ruv_range.len(),
+                    max_clock_skew.as_secs()
+                ),
+            })
+        } else {
+            error!(
+                ?skewed,
+                "changes exist from servers that are ahead of the local clock"
+            );
+            Ok(SelfTestItem {
+                check: SelfTestCheck::ClockSkew,
+                status: SelfTestStatus::Fail,
+                detail: format!(
+                    "changes are ahead of the local clock, either this clock has gone backwards or these servers are ahead: {}",
+                    skewed.join(", ")
+                ),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use kanidm_proto::internal::{SelfTestCheck, SelfTestStatus};
+
+    #[qs_test]
+    async fn test_self_test(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let max_clock_skew = Duration::from_secs(60);
+
+        let mut read_txn = server.read().await.unwrap();
+        let items = read_txn
+            .self_test(ct, max_clock_skew)
+            .expect("Unable to perform self test");
+        assert!(items.iter().all(|item| item.status == SelfTestStatus::Pass));
+        drop(read_txn);
+
+        // If our clock goes backwards, the changes we have already made are now in the future.
+        let ct = ct - Duration::from_secs(3600);
+        let mut read_txn = server.read().await.unwrap();
+        let items = read_txn
+            .self_test(ct, max_clock_skew)
+            .expect("Unable to perform self test");
+        assert!(items.iter().any(|item| item.status == SelfTestStatus::Fail));
+    }
+
+    #[qs_test]
+    async fn test_readiness_check(server: &QueryServer) {
+        let mut read_txn = server.read().await.unwrap();
+        let items = read_txn.readiness_check();
+        assert!(items
+            .iter()
+            .any(|item| item.check == SelfTestCheck::Database));
+        assert!(items.iter().all(|item| item.status == SelfTestStatus::Pass));
+    }
+}
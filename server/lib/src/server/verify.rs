@@ -0,0 +1,303 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::plugins::Plugins;
+use crate::prelude::*;
+
+/// The area of the database that a verification issue was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyCategory {
+    Backend,
+    Index,
+    Schema,
+    ReferentialIntegrity,
+    MemberOf,
+    Attribute,
+    Replication,
+    KeyObject,
+}
+
+impl fmt::Display for VerifyCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyCategory::Backend => write!(f, "backend"),
+            VerifyCategory::Index => write!(f, "index"),
+            VerifyCategory::Schema => write!(f, "schema"),
+            VerifyCategory::ReferentialIntegrity => write!(f, "referential integrity"),
+            VerifyCategory::MemberOf => write!(f, "memberof"),
+            VerifyCategory::Attribute => write!(f, "attribute"),
+            VerifyCategory::Replication => write!(f, "replication"),
+            VerifyCategory::KeyObject => write!(f, "key object"),
+        }
+    }
+}
+
+/// An automated fix that is safe to apply to an offline database. Repairs are applied in
+/// the order they are declared here, since a reindex must precede any search that relies
+/// on the indexes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VerifyRepair {
+    Reindex,
+    RemoveDanglingReferences,
+    RecalculateMemberOf,
+}
+
+impl fmt::Display for VerifyRepair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyRepair::Reindex => write!(f, "rebuild all indexes"),
+            VerifyRepair::RemoveDanglingReferences => {
+                write!(f, "remove references to entries that no longer exist")
+            }
+            VerifyRepair::RecalculateMemberOf => write!(f, "recalculate memberof of all entries"),
+        }
+    }
+}
+
+/// A single problem found while verifying the database.
+#[derive(Debug, Clone)]
+pub struct VerifyIssue {
+    pub category: VerifyCategory,
+    /// The entry affected by this issue, if it could be determined.
+    pub uuid: Option<Uuid>,
+    pub detail: String,
+    /// The automated repair for this issue, if one is safe to apply.
+    pub repair: Option<VerifyRepair>,
+}
+
+impl VerifyIssue {
+    fn from_consistency_error(err: ConsistencyError, id_map: &BTreeMap<u64, Uuid>) -> Self {
+        let (category, id, repair) = match &err {
+            ConsistencyError::Unknown
+            | ConsistencyError::QueryServerSearchFailure
+            | ConsistencyError::SqliteIntegrityFailure
+            | ConsistencyError::PostgresIntegrityFailure
+            | ConsistencyError::BackendAllIdsSync
+            | ConsistencyError::UuidNotUnique(_) => (VerifyCategory::Backend, None, None),
+            ConsistencyError::EntryUuidCorrupt(id) => (VerifyCategory::Backend, Some(*id), None),
+            ConsistencyError::BackendIndexSync | ConsistencyError::UuidIndexCorrupt(_) => {
+                (VerifyCategory::Index, None, Some(VerifyRepair::Reindex))
+            }
+            ConsistencyError::SchemaClassMissingAttribute(_, _)
+            | ConsistencyError::SchemaClassPhantomAttribute(_, _)
+            | ConsistencyError::SchemaUuidNotUnique(_)
+            | ConsistencyError::InvalidAttributeType(_) => (VerifyCategory::Schema, None, None),
+            ConsistencyError::RefintNotUpheld(id) => (
+                VerifyCategory::ReferentialIntegrity,
+                Some(*id),
+                Some(VerifyRepair::RemoveDanglingReferences),
+            ),
+            ConsistencyError::MemberOfInvalid(id) => (
+                VerifyCategory::MemberOf,
+                Some(*id),
+                Some(VerifyRepair::RecalculateMemberOf),
+            ),
+            ConsistencyError::InvalidSpn(id) => (VerifyCategory::Attribute, Some(*id), None),
+            ConsistencyError::DuplicateUniqueAttribute | ConsistencyError::DeniedName(_) => {
+                (VerifyCategory::Attribute, None, None)
+            }
+            ConsistencyError::ChangelogDesynchronised(id)
+            | ConsistencyError::ChangeStateDesynchronised(id) => {
+                (VerifyCategory::Replication, Some(*id), None)
+            }
+            ConsistencyError::RuvInconsistent(_) => (VerifyCategory::Replication, None, None),
+            ConsistencyError::KeyProviderUuidMissing { .. }
+            | ConsistencyError::KeyProviderNoKeys { .. }
+            | ConsistencyError::KeyProviderNotFound { .. } => {
+                (VerifyCategory::KeyObject, None, None)
+            }
+        };
+
+        let uuid = match &err {
+            ConsistencyError::SchemaUuidNotUnique(u) | ConsistencyError::DeniedName(u) => Some(*u),
+            ConsistencyError::KeyProviderUuidMissing { key_object }
+            | ConsistencyError::KeyProviderNoKeys { key_object }
+            | ConsistencyError::KeyProviderNotFound { key_object, .. } => Some(*key_object),
+            _ => id.and_then(|id| id_map.get(&id).copied()),
+        };
+
+        VerifyIssue {
+            category,
+            uuid,
+            detail: format!("{:?}", err),
+            repair,
+        }
+    }
+}
+
+impl QueryServer {
+    /// Verify the database, and describe each issue found along with the entry it affects
+    /// and how it may be repaired. Unlike [QueryServer::verify] this also checks that every
+    /// entry still conforms to the schema stored in the database. Nothing is written.
+    pub async fn verify_report(&self, ct: Duration) -> Vec<VerifyIssue> {
+        let mut issues = match self.read().await {
+            Ok(mut r_txn) => r_txn.verify_report(),
+            Err(_) => {
+                return vec![VerifyIssue::from_consistency_error(
+                    ConsistencyError::Unknown,
+                    &BTreeMap::new(),
+                )]
+            }
+        };
+
+        // The schema check needs the schema loaded from the database, which is only
+        // possible in a write transaction. It's never committed.
+        let schema_issues = match self.write(ct).await {
+            Ok(mut w_txn) => w_txn.verify_schema_report(),
+            Err(err) => Err(err),
+        };
+
+        match schema_issues {
+            Ok(schema_issues) => issues.extend(schema_issues),
+            Err(err) => {
+                error!(?err, "Unable to verify entries against the database schema");
+                issues.push(VerifyIssue {
+                    category: VerifyCategory::Schema,
+                    uuid: None,
+                    detail: format!("unable to load schema - {:?}", err),
+                    repair: None,
+                });
+            }
+        }
+
+        issues
+    }
+}
+
+impl QueryServerReadTransaction<'_> {
+    /// Run the consistency checks of [QueryServerReadTransaction::verify], resolving the
+    /// entry that each issue affects.
+    pub fn verify_report(&mut self) -> Vec<VerifyIssue> {
+        let errors: Vec<_> = self.verify().into_iter().filter_map(|r| r.err()).collect();
+
+        if errors.is_empty() {
+            return Vec::with_capacity(0);
+        }
+
+        let id_map: BTreeMap<u64, Uuid> = self
+            .internal_search(filter_all!(f_pres(Attribute::Class)))
+            .map(|entries| entries.iter().map(|e| (e.get_id(), e.get_uuid())).collect())
+            .unwrap_or_else(|err| {
+                error!(?err, "Unable to resolve entry uuids during verification");
+                BTreeMap::new()
+            });
+
+        errors
+            .into_iter()
+            .map(|err| VerifyIssue::from_consistency_error(err, &id_map))
+            .collect()
+    }
+}
+
+impl QueryServerWriteTransaction<'_> {
+    /// Check that every live and recycled entry conforms to the schema stored in the
+    /// database. Tombstones have had their content removed, so they are exempt.
+    pub fn verify_schema_report(&mut self) -> Result<Vec<VerifyIssue>, OperationError> {
+        self.reload_schema()?;
+
+        let all_entries = self.internal_search(filter_all!(f_pres(Attribute::Class)))?;
+
+        let schema = self.get_schema();
+        let issues = all_entries
+            .iter()
+            .filter(|e| !e.attribute_equality(Attribute::Class, &EntryClass::Tombstone.into()))
+            .filter_map(|entry| {
+                entry.verify_schema(schema).err().map(|err| VerifyIssue {
+                    category: VerifyCategory::Schema,
+                    uuid: Some(entry.get_uuid()),
+                    detail: format!("{:?}", err),
+                    repair: None,
+                })
+            })
+            .collect();
+
+        Ok(issues)
+    }
+
+    /// Apply the requested repairs. These are only the fixes that can't discard any
+    /// information that isn't already invalid, and the database should be verified again
+    /// once they are committed.
+    pub fn verify_repair(
+        &mut self,
+        repairs: &BTreeSet<VerifyRepair>,
+    ) -> Result<(), OperationError> {
+        for repair in repairs {
+            info!(%repair, "applying repair");
+            match repair {
+                VerifyRepair::Reindex => self.reindex(true)?,
+                VerifyRepair::RemoveDanglingReferences => {
+                    let missing = Plugins::run_repair_dangling_references(self)?;
+                    info!(?missing, "removed references to missing entries");
+                }
+                VerifyRepair::RecalculateMemberOf => Plugins::run_repair_memberof(self)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VerifyCategory, VerifyRepair};
+    use crate::prelude::*;
+    use std::collections::BTreeSet;
+
+    #[qs_test]
+    async fn test_verify_repair_memberof(server: &QueryServer) {
+        let group_uuid = Uuid::new_v4();
+        let person_uuid = Uuid::new_v4();
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let e_person = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::Uuid, Value::Uuid(person_uuid)),
+            (Attribute::Description, Value::new_utf8s("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson"))
+        );
+
+        let e_group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Uuid, Value::Uuid(group_uuid)),
+            (Attribute::Member, Value::Refer(person_uuid))
+        );
+
+        assert!(server_txn.internal_create(vec![e_person, e_group]).is_ok());
+
+        // Strip the memberof from the person behind the plugins back.
+        let filt = filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(person_uuid)));
+        let mut work_set = server_txn
+            .internal_search_writeable(&filt)
+            .expect("Failed to perform internal search writeable");
+        for (_, entry) in work_set.iter_mut() {
+            entry.purge_ava(Attribute::MemberOf);
+            entry.purge_ava(Attribute::DirectMemberOf);
+        }
+        assert!(server_txn.internal_apply_writable(work_set).is_ok());
+        server_txn.commit().expect("Failed to commit");
+
+        let issues = server.verify_report(duration_from_epoch_now()).await;
+
+        assert!(issues
+            .iter()
+            .any(|issue| issue.category == VerifyCategory::MemberOf
+                && issue.uuid == Some(person_uuid)
+                && issue.repair == Some(VerifyRepair::RecalculateMemberOf)));
+
+        let repairs: BTreeSet<_> = issues.iter().filter_map(|issue| issue.repair).collect();
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        assert!(server_txn.verify_repair(&repairs).is_ok());
+        server_txn.commit().expect("Failed to commit");
+
+        assert!(server
+            .verify_report(duration_from_epoch_now())
+            .await
+            .is_empty());
+    }
+}
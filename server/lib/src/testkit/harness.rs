@@ -0,0 +1,446 @@
+//! A deterministic in-memory server for tests. This is available to other crates with the
+//! `test` feature, so that projects integrating with Kanidm can test against a real server
+//! without needing to run one.
+//!
+//! Time in the harness only moves when the test advances it, and fixtures derive their uuids
+//! from their names so that the same test always produces the same entries.
+//!
+//! ```ignore
+//! let mut harness = TestHarness::new().await;
+//!
+//! let person = PersonFixture::new("alice").set_password("correct horse battery staple");
+//! let group = GroupFixture::new("staff").add_member(person.uuid());
+//! let client = OAuth2ClientFixture::new("portal", "https://portal.example.com")
+//!     .add_scope_map(group.uuid(), &["openid", "groups"]);
+//!
+//! harness
+//!     .create(vec![person.into(), group.into(), client.into()])
+//!     .await?;
+//!
+//! harness.advance(Duration::from_secs(3600));
+//! ```
+
+use std::collections::BTreeSet;
+
+use kanidm_lib_crypto::CryptoPolicy;
+
+use crate::credential::Credential;
+use crate::idm::audit::AuditEvent;
+use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction};
+use crate::prelude::*;
+use crate::testkit::{setup_test_at, TestConfiguration};
+
+/// The time that the harness starts at.
+pub const TEST_HARNESS_START_TIME: Duration = Duration::from_secs(1_700_000_000);
+
+/// Derive a stable uuid for a fixture from its name.
+fn fixture_uuid(kind: &str, name: &str) -> Uuid {
+    let digest = openssl::sha::sha256(format!("{}:{}", kind, name).as_bytes());
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    uuid::Builder::from_custom_bytes(bytes).into_uuid()
+}
+
+pub struct PersonFixture {
+    uuid: Uuid,
+    name: String,
+    display_name: String,
+    legal_name: Option<String>,
+    mail: Vec<String>,
+    credential: Option<Credential>,
+}
+
+impl PersonFixture {
+    pub fn new(name: &str) -> Self {
+        PersonFixture {
+            uuid: fixture_uuid("person", name),
+            name: name.to_string(),
+            display_name: name.to_string(),
+            legal_name: None,
+            mail: Vec::with_capacity(0),
+            credential: None,
+        }
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    pub fn set_uuid(mut self, uuid: Uuid) -> Self {
+        self.uuid = uuid;
+        self
+    }
+
+    pub fn set_display_name(mut self, display_name: &str) -> Self {
+        self.display_name = display_name.to_string();
+        self
+    }
+
+    pub fn set_legal_name(mut self, legal_name: &str) -> Self {
+        self.legal_name = Some(legal_name.to_string());
+        self
+    }
+
+    /// Set the mail addresses of this person. The first address is the primary address.
+    pub fn set_mail(mut self, mail: &[&str]) -> Self {
+        self.mail = mail.iter().map(|m| m.to_string()).collect();
+        self
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn set_password(mut self, password: &str) -> Self {
+        let policy = CryptoPolicy::danger_test_minimum();
+        let cred = Credential::new_password_only(&policy, password)
+            .expect("Unable to create password credential");
+        self.credential = Some(cred);
+        self
+    }
+}
+
+impl From<PersonFixture> for EntryInitNew {
+    fn from(fixture: PersonFixture) -> Self {
+        let mut entry = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Uuid, Value::Uuid(fixture.uuid)),
+            (Attribute::Name, Value::new_iname(&fixture.name)),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s(&fixture.display_name)
+            )
+        );
+
+        if let Some(legal_name) = &fixture.legal_name {
+            entry.add_ava(Attribute::LegalName, Value::new_utf8s(legal_name));
+        }
+
+        for (i, mail) in fixture.mail.iter().enumerate() {
+            let value = if i == 0 {
+                Value::new_email_address_primary_s(mail)
+            } else {
+                Value::new_email_address_s(mail)
+            };
+            if let Some(value) = value {
+                entry.add_ava(Attribute::Mail, value);
+            } else {
+                warn!(?mail, "Ignoring invalid mail address in person fixture");
+            }
+        }
+
+        if let Some(cred) = fixture.credential {
+            entry.add_ava(
+                Attribute::PrimaryCredential,
+                Value::new_credential("primary", cred),
+            );
+        }
+
+        entry
+    }
+}
+
+pub struct GroupFixture {
+    uuid: Uuid,
+    name: String,
+    description: Option<String>,
+    members: Vec<Uuid>,
+}
+
+impl GroupFixture {
+    pub fn new(name: &str) -> Self {
+        GroupFixture {
+            uuid: fixture_uuid("group", name),
+            name: name.to_string(),
+            description: None,
+            members: Vec::with_capacity(0),
+        }
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    pub fn set_uuid(mut self, uuid: Uuid) -> Self {
+        self.uuid = uuid;
+        self
+    }
+
+    pub fn set_description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn add_member(mut self, member: Uuid) -> Self {
+        self.members.push(member);
+        self
+    }
+}
+
+impl From<GroupFixture> for EntryInitNew {
+    fn from(fixture: GroupFixture) -> Self {
+        let mut entry = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Uuid, Value::Uuid(fixture.uuid)),
+            (Attribute::Name, Value::new_iname(&fixture.name))
+        );
+
+        if let Some(description) = &fixture.description {
+            entry.add_ava(Attribute::Description, Value::new_utf8s(description));
+        }
+
+        for member in fixture.members {
+            entry.add_ava(Attribute::Member, Value::Refer(member));
+        }
+
+        entry
+    }
+}
+
+pub struct OAuth2ClientFixture {
+    uuid: Uuid,
+    name: String,
+    landing: Url,
+    redirect_urls: Vec<Url>,
+    public: bool,
+    scope_maps: Vec<(Uuid, BTreeSet<String>)>,
+}
+
+impl OAuth2ClientFixture {
+    /// A confidential client. The landing url is also allowed as a redirect url.
+    #[allow(clippy::expect_used)]
+    pub fn new(name: &str, landing: &str) -> Self {
+        OAuth2ClientFixture {
+            uuid: fixture_uuid("oauth2_client", name),
+            name: name.to_string(),
+            landing: Url::parse(landing).expect("Invalid landing url"),
+            redirect_urls: Vec::with_capacity(0),
+            public: false,
+            scope_maps: Vec::with_capacity(0),
+        }
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    pub fn set_uuid(mut self, uuid: Uuid) -> Self {
+        self.uuid = uuid;
+        self
+    }
+
+    /// Make this a public client, which has no client secret.
+    pub fn set_public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+
+    #[allow(clippy::expect_used)]
+    pub fn add_redirect_url(mut self, url: &str) -> Self {
+        self.redirect_urls
+            .push(Url::parse(url).expect("Invalid redirect url"));
+        self
+    }
+
+    pub fn add_scope_map(mut self, group: Uuid, scopes: &[&str]) -> Self {
+        self.scope_maps
+            .push((group, scopes.iter().map(|s| s.to_string()).collect()));
+        self
+    }
+}
+
+impl From<OAuth2ClientFixture> for EntryInitNew {
+    fn from(fixture: OAuth2ClientFixture) -> Self {
+        let client_class = if fixture.public {
+            EntryClass::OAuth2ResourceServerPublic
+        } else {
+            EntryClass::OAuth2ResourceServerBasic
+        };
+
+        let mut entry = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (Attribute::Class, client_class.to_value()),
+            (Attribute::Uuid, Value::Uuid(fixture.uuid)),
+            (Attribute::Name, Value::new_iname(&fixture.name)),
+            (Attribute::DisplayName, Value::new_utf8s(&fixture.name)),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::Url(fixture.landing.clone())
+            ),
+            (Attribute::OAuth2RsOrigin, Value::Url(fixture.landing))
+        );
+
+        for url in fixture.redirect_urls {
+            entry.add_ava(Attribute::OAuth2RsOrigin, Value::Url(url));
+        }
+
+        for (group, scopes) in fixture.scope_maps {
+            if let Some(value) = Value::new_oauthscopemap(group, scopes) {
+                entry.add_ava(Attribute::OAuth2RsScopeMap, value);
+            } else {
+                warn!(
+                    ?group,
+                    "Ignoring invalid scope map in oauth2 client fixture"
+                );
+            }
+        }
+
+        entry
+    }
+}
+
+pub struct TestHarness {
+    idms: IdmServer,
+    idms_delayed: IdmServerDelayed,
+    idms_audit: IdmServerAudit,
+    ct: Duration,
+}
+
+impl TestHarness {
+    pub async fn new() -> Self {
+        Self::with_config(TestConfiguration::default()).await
+    }
+
+    #[allow(clippy::expect_used)]
+    pub async fn with_config(config: TestConfiguration) -> Self {
+        let ct = TEST_HARNESS_START_TIME;
+        let qs = setup_test_at(config, ct).await;
+
+        let (idms, idms_delayed, idms_audit) = IdmServer::new(qs, "https://idm.example.com", true)
+            .await
+            .expect("Failed to setup idms");
+
+        TestHarness {
+            idms,
+            idms_delayed,
+            idms_audit,
+            ct,
+        }
+    }
+
+    pub fn idms(&self) -> &IdmServer {
+        &self.idms
+    }
+
+    /// The current time of the harness.
+    pub fn now(&self) -> Duration {
+        self.ct
+    }
+
+    /// Move the current time of the harness forward.
+    pub fn advance(&mut self, duration: Duration) {
+        self.ct += duration;
+    }
+
+    pub async fn proxy_read(&self) -> Result<IdmServerProxyReadTransaction<'_>, OperationError> {
+        self.idms.proxy_read().await
+    }
+
+    /// Begin a write at the current time of the harness.
+    pub async fn proxy_write(&self) -> Result<IdmServerProxyWriteTransaction<'_>, OperationError> {
+        self.idms.proxy_write(self.ct).await
+    }
+
+    /// Create entries, such as from fixtures, in a single transaction.
+    pub async fn create(&self, entries: Vec<EntryInitNew>) -> Result<(), OperationError> {
+        let mut idms_prox_write = self.proxy_write().await?;
+        idms_prox_write.qs_write.internal_create(entries)?;
+        idms_prox_write.commit()
+    }
+
+    /// An identity for the entry with `uuid`, for performing operations as that entry
+    /// with its access controls applied.
+    pub async fn identity(&self, uuid: Uuid) -> Result<Identity, OperationError> {
+        let mut idms_prox_read = self.proxy_read().await?;
+        idms_prox_read
+            .qs_read
+            .internal_search_uuid(uuid)
+            .map(Identity::from_impersonate_entry_readwrite)
+    }
+
+    /// The client secret of a confidential oauth2 client.
+    pub async fn oauth2_basic_secret(&self, uuid: Uuid) -> Result<String, OperationError> {
+        let mut idms_prox_read = self.proxy_read().await?;
+        let entry = idms_prox_read.qs_read.internal_search_uuid(uuid)?;
+        entry
+            .get_ava_single_secret(Attribute::OAuth2RsBasicSecret)
+            .map(str::to_string)
+            .ok_or(OperationError::NoMatchingAttributes)
+    }
+
+    /// Apply the actions that the server has deferred, such as updating the last used time
+    /// of a credential. Returns the number of actions that were applied.
+    pub async fn process_delayed(&mut self) -> Result<usize, OperationError> {
+        let mut count = 0;
+        while let Ok(da) = self.idms_delayed.try_recv() {
+            let mut idms_prox_write = self.idms.proxy_write(self.ct).await?;
+            idms_prox_write.process_delayedaction(&da, self.ct)?;
+            idms_prox_write.commit()?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Take the audit events that have been emitted since the last call.
+    pub fn drain_audit_events(&mut self) -> Vec<AuditEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.idms_audit.audit_rx().try_recv() {
+            events.push(event);
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GroupFixture, OAuth2ClientFixture, PersonFixture, TestHarness};
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_harness_fixtures() {
+        let mut harness = TestHarness::new().await;
+        let start = harness.now();
+
+        let person = PersonFixture::new("alice")
+            .set_mail(&["alice@example.com"])
+            .set_password("correct horse battery staple");
+        let group = GroupFixture::new("staff").add_member(person.uuid());
+        let client = OAuth2ClientFixture::new("portal", "https://portal.example.com")
+            .add_scope_map(group.uuid(), &["openid", "groups"]);
+
+        // Fixture uuids are stable between runs.
+        assert_eq!(person.uuid(), PersonFixture::new("alice").uuid());
+        assert_ne!(person.uuid(), GroupFixture::new("alice").uuid());
+
+        let person_uuid = person.uuid();
+        let group_uuid = group.uuid();
+        let client_uuid = client.uuid();
+
+        harness
+            .create(vec![person.into(), group.into(), client.into()])
+            .await
+            .expect("Failed to create fixtures");
+
+        assert!(harness.oauth2_basic_secret(client_uuid).await.is_ok());
+
+        let mut idms_prox_read = harness.proxy_read().await.expect("Failed to begin read");
+        let entry = idms_prox_read
+            .qs_read
+            .internal_search_uuid(person_uuid)
+            .expect("Person not found");
+        assert!(entry.attribute_equality(Attribute::MemberOf, &PartialValue::Refer(group_uuid)));
+        drop(idms_prox_read);
+
+        assert!(harness.identity(person_uuid).await.is_ok());
+
+        harness.advance(Duration::from_secs(60));
+        assert_eq!(harness.now(), start + Duration::from_secs(60));
+
+        assert!(matches!(harness.process_delayed().await, Ok(0)));
+    }
+}
@@ -2,6 +2,9 @@ use crate::be::{Backend, BackendConfig};
 use crate::prelude::*;
 use crate::schema::Schema;
 
+#[cfg(any(test, feature = "test"))]
+pub mod harness;
+
 pub struct TestConfiguration {
     pub domain_level: DomainVersion,
 }
@@ -14,8 +17,13 @@ impl Default for TestConfiguration {
     }
 }
 
-#[allow(clippy::expect_used)]
 pub async fn setup_test(config: TestConfiguration) -> QueryServer {
+    setup_test_at(config, duration_from_epoch_now()).await
+}
+
+/// Setup a test server that is initialised at `ct` rather than the current time.
+#[allow(clippy::expect_used)]
+pub async fn setup_test_at(config: TestConfiguration, ct: Duration) -> QueryServer {
     sketching::test_init();
 
     // Create an in memory BE
@@ -31,7 +39,7 @@ pub async fn setup_test(config: TestConfiguration) -> QueryServer {
         .expect("Failed to setup Query Server");
 
     test_server
-        .initialise_helper(duration_from_epoch_now(), config.domain_level)
+        .initialise_helper(ct, config.domain_level)
         .await
         .expect("init failed!");
 
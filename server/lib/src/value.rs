@@ -33,6 +33,7 @@ use webauthn_rs::prelude::{
 };
 
 use crate::be::dbentry::DbIdentSpn;
+use crate::be::dbvalue::DbValueOauth2SessionRevokeReasonV1;
 use crate::be::dbvalue::DbValueOauthClaimMapJoinV1;
 use crate::credential::{apppwd::ApplicationPassword, totp::Totp, Credential};
 use crate::prelude::*;
@@ -42,7 +43,9 @@ use crate::server::keys::KeyId;
 use crate::valueset::image::ImageValueThings;
 use crate::valueset::uuid_to_proto_string;
 
-use kanidm_proto::internal::{ApiTokenPurpose, Filter as ProtoFilter, UiHint};
+use kanidm_proto::internal::{
+    ApiTokenPermission as ProtoApiTokenPermission, ApiTokenPurpose, Filter as ProtoFilter, UiHint,
+};
 use kanidm_proto::scim_v1::ScimOauth2ClaimMapJoinChar;
 use kanidm_proto::v1::UatPurposeStatus;
 use std::hash::Hash;
@@ -151,10 +154,13 @@ pub enum IntentTokenState {
     Valid {
         max_ttl: Duration,
         perms: CredUpdateSessionPerms,
+        // How many more times this intent may be used to commit a credential update.
+        uses_remaining: u32,
     },
     InProgress {
         max_ttl: Duration,
         perms: CredUpdateSessionPerms,
+        uses_remaining: u32,
         session_id: Uuid,
         session_ttl: Duration,
     },
@@ -284,6 +290,8 @@ pub enum SyntaxType {
     HexString = 39,
     Certificate = 40,
     ApplicationPassword = 41,
+    CredentialUsage = 42,
+    PublicBinary = 43,
 }
 
 impl TryFrom<&str> for SyntaxType {
@@ -334,6 +342,8 @@ impl TryFrom<&str> for SyntaxType {
             "HEX_STRING" => Ok(SyntaxType::HexString),
             "CERTIFICATE" => Ok(SyntaxType::Certificate),
             "APPLICATION_PASSWORD" => Ok(SyntaxType::ApplicationPassword),
+            "CREDENTIAL_USAGE" => Ok(SyntaxType::CredentialUsage),
+            "PUBLIC_BINARY" => Ok(SyntaxType::PublicBinary),
             _ => Err(()),
         }
     }
@@ -384,6 +394,8 @@ impl fmt::Display for SyntaxType {
             SyntaxType::HexString => "HEX_STRING",
             SyntaxType::Certificate => "CERTIFICATE",
             SyntaxType::ApplicationPassword => "APPLICATION_PASSWORD",
+            SyntaxType::CredentialUsage => "CREDENTIAL_USAGE",
+            SyntaxType::PublicBinary => "PUBLIC_BINARY",
         })
     }
 }
@@ -969,6 +981,61 @@ pub struct ApiToken {
     pub issued_at: OffsetDateTime,
     pub issued_by: IdentityId,
     pub scope: ApiTokenScope,
+    pub permissions: Vec<ApiTokenPermission>,
+}
+
+/// A permission of a scoped api token. The token may only modify these attributes of
+/// entries that are of this class, and whose name starts with the prefix if one is set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiTokenPermission {
+    pub class: String,
+    pub name_prefix: Option<String>,
+    pub attrs: BTreeSet<Attribute>,
+}
+
+impl ApiTokenPermission {
+    pub fn entry_match(&self, entry: &EntrySealedCommitted) -> bool {
+        let class_match =
+            entry.attribute_equality(Attribute::Class, &PartialValue::new_class(&self.class));
+
+        let name_match = match &self.name_prefix {
+            Some(name_prefix) => entry
+                .get_ava_single_iname(Attribute::Name)
+                .map(|name| name.starts_with(name_prefix.as_str()))
+                .unwrap_or(false),
+            None => true,
+        };
+
+        class_match && name_match
+    }
+}
+
+impl From<&ProtoApiTokenPermission> for ApiTokenPermission {
+    fn from(permission: &ProtoApiTokenPermission) -> Self {
+        ApiTokenPermission {
+            class: permission.class.to_lowercase(),
+            name_prefix: permission.name_prefix.clone(),
+            attrs: permission
+                .attrs
+                .iter()
+                .map(|attr| Attribute::from(attr.as_str()))
+                .collect(),
+        }
+    }
+}
+
+impl From<&ApiTokenPermission> for ProtoApiTokenPermission {
+    fn from(permission: &ApiTokenPermission) -> Self {
+        ProtoApiTokenPermission {
+            class: permission.class.clone(),
+            name_prefix: permission.name_prefix.clone(),
+            attrs: permission
+                .attrs
+                .iter()
+                .map(|attr| attr.as_str().to_string())
+                .collect(),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -1057,6 +1124,11 @@ pub enum AuthType {
     PasswordSecurityKey,
     Passkey,
     AttestedPasskey,
+    EmailLink,
+    Federated,
+    JwtAssertion,
+    /// The session was issued to a member of idm_impersonation_admins, not by authenticating.
+    Impersonation,
 }
 
 impl fmt::Display for AuthType {
@@ -1070,10 +1142,62 @@ impl fmt::Display for AuthType {
             AuthType::PasswordSecurityKey => write!(f, "passwordsecuritykey"),
             AuthType::Passkey => write!(f, "passkey"),
             AuthType::AttestedPasskey => write!(f, "attested_passkey"),
+            AuthType::EmailLink => write!(f, "email_link"),
+            AuthType::Federated => write!(f, "federated"),
+            AuthType::JwtAssertion => write!(f, "jwt_assertion"),
+            AuthType::Impersonation => write!(f, "impersonation"),
+        }
+    }
+}
+
+/// A factor of a credential that was presented during an authentication. A multi factor
+/// credential records the use of each factor that was presented.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CredentialFactor {
+    Password,
+    Totp(String),
+    SecurityKey,
+    BackupCode,
+    Passkey,
+    /// An api token, where the credential is identified by the token id.
+    ApiToken,
+    /// An auth session, where the credential is identified by the session id.
+    Session,
+    /// A link that was sent to the mail address of the account.
+    EmailLink,
+    /// A login with an upstream identity provider.
+    Federated,
+    /// A jwt assertion, signed by the registered key with this label.
+    JwtAssertion(String),
+}
+
+impl fmt::Display for CredentialFactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialFactor::Password => write!(f, "password"),
+            CredentialFactor::Totp(label) => write!(f, "totp ({})", label),
+            CredentialFactor::SecurityKey => write!(f, "security key"),
+            CredentialFactor::BackupCode => write!(f, "backup code"),
+            CredentialFactor::Passkey => write!(f, "passkey"),
+            CredentialFactor::ApiToken => write!(f, "api token"),
+            CredentialFactor::Session => write!(f, "session"),
+            CredentialFactor::EmailLink => write!(f, "email link"),
+            CredentialFactor::Federated => write!(f, "upstream identity provider"),
+            CredentialFactor::JwtAssertion(label) => write!(f, "jwt assertion ({})", label),
         }
     }
 }
 
+/// How often, when and from where a credential factor was last used to authenticate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialUsage {
+    pub last_used: OffsetDateTime,
+    pub use_count: u64,
+    pub source: Option<String>,
+    /// The user agent of the client that authenticated, if known.
+    pub user_agent: Option<String>,
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub struct Session {
     pub label: String,
@@ -1187,12 +1311,57 @@ pub struct Oauth2Session {
     pub state: SessionState,
     pub issued_at: OffsetDateTime,
     pub rs_uuid: Uuid,
+    pub revoke_reason: Option<Oauth2SessionRevokeReason>,
+}
+
+/// Why an oauth2 session was revoked, when it was revoked by the server rather than by
+/// a logout or an administrator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Oauth2SessionRevokeReason {
+    /// A refresh token that had already been exchanged was presented again, so the
+    /// token family is assumed to be compromised.
+    RefreshTokenReuse,
+}
+
+impl From<DbValueOauth2SessionRevokeReasonV1> for Oauth2SessionRevokeReason {
+    fn from(value: DbValueOauth2SessionRevokeReasonV1) -> Oauth2SessionRevokeReason {
+        match value {
+            DbValueOauth2SessionRevokeReasonV1::RefreshTokenReuse => {
+                Oauth2SessionRevokeReason::RefreshTokenReuse
+            }
+        }
+    }
+}
+
+impl From<Oauth2SessionRevokeReason> for kanidm_proto::v1::Oauth2SessionRevokeReason {
+    fn from(value: Oauth2SessionRevokeReason) -> kanidm_proto::v1::Oauth2SessionRevokeReason {
+        match value {
+            Oauth2SessionRevokeReason::RefreshTokenReuse => {
+                kanidm_proto::v1::Oauth2SessionRevokeReason::RefreshTokenReuse
+            }
+        }
+    }
+}
+
+impl From<Oauth2SessionRevokeReason> for DbValueOauth2SessionRevokeReasonV1 {
+    fn from(value: Oauth2SessionRevokeReason) -> DbValueOauth2SessionRevokeReasonV1 {
+        match value {
+            Oauth2SessionRevokeReason::RefreshTokenReuse => {
+                DbValueOauth2SessionRevokeReasonV1::RefreshTokenReuse
+            }
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum KeyUsage {
     JwsEs256,
     JweA128GCM,
+    ReplTrustAnchor,
+    SshCa,
+    Saml2Signing,
+    Kerberos,
+    JwsRs256,
 }
 
 impl fmt::Display for KeyUsage {
@@ -1203,6 +1372,11 @@ impl fmt::Display for KeyUsage {
             match self {
                 KeyUsage::JwsEs256 => "jws_es256",
                 KeyUsage::JweA128GCM => "jwe_a128gcm",
+                KeyUsage::ReplTrustAnchor => "repl_trust_anchor",
+                KeyUsage::SshCa => "ssh_ca",
+                KeyUsage::Saml2Signing => "saml2_signing",
+                KeyUsage::Kerberos => "kerberos",
+                KeyUsage::JwsRs256 => "jws_rs256",
             }
         )
     }
@@ -1301,6 +1475,7 @@ pub enum Value {
 
     Certificate(Box<Certificate>),
     ApplicationPassword(ApplicationPassword),
+    CredentialUsage(Uuid, CredentialFactor, CredentialUsage),
 }
 
 impl PartialEq for Value {
@@ -2119,6 +2294,22 @@ impl Value {
             Value::ApplicationPassword(ap) => {
                 Value::validate_str_escapes(&ap.label) && Value::validate_singleline(&ap.label)
             }
+            Value::CredentialUsage(_, factor, usage) => {
+                let label_valid = match factor {
+                    CredentialFactor::Totp(label) | CredentialFactor::JwtAssertion(label) => {
+                        Value::validate_str_escapes(label) && Value::validate_singleline(label)
+                    }
+                    _ => true,
+                };
+                let source_valid = usage.source.as_ref().map_or(true, |source| {
+                    Value::validate_str_escapes(source) && Value::validate_singleline(source)
+                });
+                let user_agent_valid = usage.user_agent.as_ref().map_or(true, |user_agent| {
+                    Value::validate_str_escapes(user_agent)
+                        && Value::validate_singleline(user_agent)
+                });
+                label_valid && source_valid && user_agent_valid
+            }
 
             // These have stricter validators so not needed.
             Value::Nsuniqueid(s) => NSUNIQUEID_RE.is_match(s),
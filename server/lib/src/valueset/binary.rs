@@ -259,11 +259,7 @@ impl ValueSetT for ValueSetPublicBinary {
     }
 
     fn syntax(&self) -> SyntaxType {
-        // Apparently I never actually implemented this type in ... anything?
-        // We should probably clean up syntax soon .....
-        //
-        // SyntaxType::PublicBinary
-        unreachable!()
+        SyntaxType::PublicBinary
     }
 
     fn validate(&self, _schema_attr: &SchemaAttribute) -> bool {
@@ -234,8 +234,10 @@ impl ValueSetIntentToken {
                         attested_passkeys_can_edit,
                         unixcred_can_edit,
                         sshpubkey_can_edit,
+                        uses_remaining,
                     } => IntentTokenState::Valid {
                         max_ttl,
+                        uses_remaining: uses_remaining.unwrap_or(1),
                         perms: CredUpdateSessionPerms {
                             ext_cred_portal_can_view,
                             primary_can_edit,
@@ -255,8 +257,10 @@ impl ValueSetIntentToken {
                         attested_passkeys_can_edit,
                         unixcred_can_edit,
                         sshpubkey_can_edit,
+                        uses_remaining,
                     } => IntentTokenState::InProgress {
                         max_ttl,
+                        uses_remaining: uses_remaining.unwrap_or(1),
                         session_id,
                         session_ttl,
                         perms: CredUpdateSessionPerms {
@@ -405,6 +409,7 @@ impl ValueSetT for ValueSetIntentToken {
                         match s {
                             IntentTokenState::Valid {
                                 max_ttl,
+                                uses_remaining,
                                 perms:
                                     CredUpdateSessionPerms {
                                         ext_cred_portal_can_view,
@@ -422,9 +427,11 @@ impl ValueSetT for ValueSetIntentToken {
                                 attested_passkeys_can_edit: *attested_passkeys_can_edit,
                                 unixcred_can_edit: *unixcred_can_edit,
                                 sshpubkey_can_edit: *sshpubkey_can_edit,
+                                uses_remaining: Some(*uses_remaining),
                             },
                             IntentTokenState::InProgress {
                                 max_ttl,
+                                uses_remaining,
                                 session_id,
                                 session_ttl,
                                 perms:
@@ -446,6 +453,7 @@ impl ValueSetT for ValueSetIntentToken {
                                 attested_passkeys_can_edit: *attested_passkeys_can_edit,
                                 unixcred_can_edit: *unixcred_can_edit,
                                 sshpubkey_can_edit: *sshpubkey_can_edit,
+                                uses_remaining: Some(*uses_remaining),
                             },
                             IntentTokenState::Consumed { max_ttl } => {
                                 DbValueIntentTokenStateV1::Consumed { max_ttl: *max_ttl }
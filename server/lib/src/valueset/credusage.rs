@@ -0,0 +1,328 @@
+use crate::be::dbvalue::{DbValueCredentialFactorV1, DbValueCredentialUsage};
+use crate::prelude::*;
+use crate::schema::SchemaAttribute;
+use crate::value::{CredentialFactor, CredentialUsage};
+use crate::valueset::{DbValueSetV2, ScimResolveStatus, ValueSet};
+use std::collections::BTreeMap;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone)]
+pub struct ValueSetCredentialUsage {
+    // The map key is the uuid of the credential that was used, and the factor of
+    // that credential.
+    map: BTreeMap<(Uuid, CredentialFactor), CredentialUsage>,
+}
+
+impl ValueSetCredentialUsage {
+    pub fn new(u: Uuid, f: CredentialFactor, c: CredentialUsage) -> Box<Self> {
+        let mut map = BTreeMap::new();
+        map.insert((u, f), c);
+        Box::new(ValueSetCredentialUsage { map })
+    }
+
+    pub fn from_dbvs2(data: Vec<DbValueCredentialUsage>) -> Result<ValueSet, OperationError> {
+        let map = data
+            .into_iter()
+            .map(|dbv| match dbv {
+                DbValueCredentialUsage::V1 {
+                    refer,
+                    factor,
+                    last_used,
+                    use_count,
+                    source,
+                    user_agent,
+                } => {
+                    let last_used = OffsetDateTime::parse(&last_used, &Rfc3339)
+                        .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+                        .map_err(|e| {
+                            admin_error!(
+                                ?e,
+                                "Invalidating credential usage {} due to invalid last_used timestamp",
+                                refer
+                            );
+                            OperationError::InvalidValueState
+                        })?;
+
+                    let factor = match factor {
+                        DbValueCredentialFactorV1::Password => CredentialFactor::Password,
+                        DbValueCredentialFactorV1::Totp(label) => CredentialFactor::Totp(label),
+                        DbValueCredentialFactorV1::SecurityKey => CredentialFactor::SecurityKey,
+                        DbValueCredentialFactorV1::BackupCode => CredentialFactor::BackupCode,
+                        DbValueCredentialFactorV1::Passkey => CredentialFactor::Passkey,
+                        DbValueCredentialFactorV1::ApiToken => CredentialFactor::ApiToken,
+                        DbValueCredentialFactorV1::Session => CredentialFactor::Session,
+                        DbValueCredentialFactorV1::EmailLink => CredentialFactor::EmailLink,
+                        DbValueCredentialFactorV1::Federated => CredentialFactor::Federated,
+                        DbValueCredentialFactorV1::JwtAssertion(label) => {
+                            CredentialFactor::JwtAssertion(label)
+                        }
+                    };
+
+                    Ok((
+                        (refer, factor),
+                        CredentialUsage {
+                            last_used,
+                            use_count,
+                            source,
+                            user_agent,
+                        },
+                    ))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Box::new(ValueSetCredentialUsage { map }))
+    }
+}
+
+impl ValueSetT for ValueSetCredentialUsage {
+    fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
+        match value {
+            Value::CredentialUsage(u, f, c) => {
+                // Each use replaces the previous record for that factor.
+                self.map.insert((u, f), c);
+                Ok(true)
+            }
+            _ => Err(OperationError::InvalidValueState),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    fn remove(&mut self, pv: &PartialValue, _cid: &Cid) -> bool {
+        match pv {
+            // Remove the usage of every factor of this credential.
+            PartialValue::Uuid(u) => {
+                let prev = self.map.len();
+                self.map.retain(|(cred_id, _), _| cred_id != u);
+                self.map.len() < prev
+            }
+            _ => false,
+        }
+    }
+
+    fn contains(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Uuid(u) => self.map.keys().any(|(cred_id, _)| cred_id == u),
+            _ => false,
+        }
+    }
+
+    fn substring(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
+    fn startswith(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
+    fn endswith(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
+    fn lessthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn generate_idx_eq_keys(&self) -> Vec<String> {
+        self.map
+            .keys()
+            .map(|(u, _)| u.as_hyphenated().to_string())
+            .collect()
+    }
+
+    fn syntax(&self) -> SyntaxType {
+        SyntaxType::CredentialUsage
+    }
+
+    fn validate(&self, _schema_attr: &SchemaAttribute) -> bool {
+        self.map.iter().all(|((_, f), c)| {
+            let label_valid = match f {
+                CredentialFactor::Totp(label) | CredentialFactor::JwtAssertion(label) => {
+                    Value::validate_str_escapes(label) && Value::validate_singleline(label)
+                }
+                _ => true,
+            };
+            let source_valid = c.source.as_ref().map_or(true, |source| {
+                Value::validate_str_escapes(source) && Value::validate_singleline(source)
+            });
+            let user_agent_valid = c.user_agent.as_ref().map_or(true, |user_agent| {
+                Value::validate_str_escapes(user_agent) && Value::validate_singleline(user_agent)
+            });
+            label_valid
+                && source_valid
+                && user_agent_valid
+                && c.last_used.offset() == time::UtcOffset::UTC
+        })
+    }
+
+    fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        Box::new(self.map.iter().map(|((u, f), c)| {
+            format!(
+                "{}: {} last used {} ({} uses)",
+                u,
+                f,
+                c.last_used
+                    .format(&Rfc3339)
+                    .unwrap_or_else(|_| c.last_used.to_string()),
+                c.use_count
+            )
+        }))
+    }
+
+    fn to_scim_value(&self) -> Option<ScimResolveStatus> {
+        None
+    }
+
+    fn to_db_valueset_v2(&self) -> DbValueSetV2 {
+        DbValueSetV2::CredentialUsage(
+            self.map
+                .iter()
+                .map(|((u, f), c)| DbValueCredentialUsage::V1 {
+                    refer: *u,
+                    factor: match f {
+                        CredentialFactor::Password => DbValueCredentialFactorV1::Password,
+                        CredentialFactor::Totp(label) => {
+                            DbValueCredentialFactorV1::Totp(label.clone())
+                        }
+                        CredentialFactor::SecurityKey => DbValueCredentialFactorV1::SecurityKey,
+                        CredentialFactor::BackupCode => DbValueCredentialFactorV1::BackupCode,
+                        CredentialFactor::Passkey => DbValueCredentialFactorV1::Passkey,
+                        CredentialFactor::ApiToken => DbValueCredentialFactorV1::ApiToken,
+                        CredentialFactor::Session => DbValueCredentialFactorV1::Session,
+                        CredentialFactor::EmailLink => DbValueCredentialFactorV1::EmailLink,
+                        CredentialFactor::Federated => DbValueCredentialFactorV1::Federated,
+                        CredentialFactor::JwtAssertion(label) => {
+                            DbValueCredentialFactorV1::JwtAssertion(label.clone())
+                        }
+                    },
+                    last_used: {
+                        debug_assert_eq!(c.last_used.offset(), time::UtcOffset::UTC);
+                        #[allow(clippy::expect_used)]
+                        c.last_used
+                            .format(&Rfc3339)
+                            .expect("Failed to format timestamp into RFC3339")
+                    },
+                    use_count: c.use_count,
+                    source: c.source.clone(),
+                    user_agent: c.user_agent.clone(),
+                })
+                .collect(),
+        )
+    }
+
+    fn to_partialvalue_iter(&self) -> Box<dyn Iterator<Item = PartialValue> + '_> {
+        Box::new(self.map.keys().map(|(u, _)| PartialValue::Uuid(*u)))
+    }
+
+    fn to_value_iter(&self) -> Box<dyn Iterator<Item = Value> + '_> {
+        Box::new(
+            self.map
+                .iter()
+                .map(|((u, f), c)| Value::CredentialUsage(*u, f.clone(), c.clone())),
+        )
+    }
+
+    fn equal(&self, other: &ValueSet) -> bool {
+        if let Some(other) = other.as_credential_usage_map() {
+            &self.map == other
+        } else {
+            debug_assert!(false);
+            false
+        }
+    }
+
+    fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
+        if let Some(b) = other.as_credential_usage_map() {
+            mergemaps!(self.map, b)
+        } else {
+            debug_assert!(false);
+            Err(OperationError::InvalidValueState)
+        }
+    }
+
+    fn as_credential_usage_map(
+        &self,
+    ) -> Option<&BTreeMap<(Uuid, CredentialFactor), CredentialUsage>> {
+        Some(&self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueSetCredentialUsage;
+    use crate::prelude::*;
+    use crate::value::{CredentialFactor, CredentialUsage};
+    use crate::valueset::from_db_valueset_v2;
+    use time::OffsetDateTime;
+
+    const TEST_CURRENT_TIME: u64 = 6000;
+
+    #[test]
+    fn test_valueset_credential_usage() {
+        let cred_id = Uuid::new_v4();
+        let usage = CredentialUsage {
+            last_used: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(TEST_CURRENT_TIME),
+            use_count: 1,
+            source: Some("203.0.113.1".to_string()),
+            user_agent: None,
+        };
+
+        let mut vs: ValueSet =
+            ValueSetCredentialUsage::new(cred_id, CredentialFactor::Password, usage.clone());
+
+        // A later use of the same factor replaces the earlier one.
+        let later = CredentialUsage {
+            use_count: 2,
+            ..usage.clone()
+        };
+        assert_eq!(
+            vs.insert_checked(Value::CredentialUsage(
+                cred_id,
+                CredentialFactor::Password,
+                later.clone()
+            )),
+            Ok(true)
+        );
+        assert_eq!(
+            vs.insert_checked(Value::CredentialUsage(
+                cred_id,
+                CredentialFactor::Totp("phone".to_string()),
+                usage
+            )),
+            Ok(true)
+        );
+        assert_eq!(
+            vs.insert_checked(Value::CredentialUsage(
+                Uuid::new_v4(),
+                CredentialFactor::Session,
+                CredentialUsage {
+                    user_agent: Some("Mozilla/5.0 (X11; Linux x86_64)".to_string()),
+                    ..later.clone()
+                }
+            )),
+            Ok(true)
+        );
+        assert_eq!(vs.len(), 3);
+
+        let map = vs.as_credential_usage_map().expect("Invalid valueset type");
+        assert_eq!(
+            map.get(&(cred_id, CredentialFactor::Password)),
+            Some(&later)
+        );
+
+        // Round trip through the db representation.
+        let vs_db = from_db_valueset_v2(vs.to_db_valueset_v2()).expect("Failed to load valueset");
+        assert!(vs.equal(&vs_db));
+
+        // Removing the credential removes every factor.
+        assert!(vs.remove(&PartialValue::Uuid(cred_id), &Cid::new_zero()));
+        assert!(vs.is_empty());
+    }
+}
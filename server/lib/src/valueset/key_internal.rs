@@ -84,6 +84,11 @@ impl ValueSetKeyInternal {
                         let usage = match usage {
                             DbValueKeyUsage::JwsEs256 => KeyUsage::JwsEs256,
                             DbValueKeyUsage::JweA128GCM => KeyUsage::JweA128GCM,
+                            DbValueKeyUsage::ReplTrustAnchor => KeyUsage::ReplTrustAnchor,
+                            DbValueKeyUsage::SshCa => KeyUsage::SshCa,
+                            DbValueKeyUsage::Saml2Signing => KeyUsage::Saml2Signing,
+                            DbValueKeyUsage::Kerberos => KeyUsage::Kerberos,
+                            DbValueKeyUsage::JwsRs256 => KeyUsage::JwsRs256,
                         };
                         let status_cid = status_cid.into();
                         let status = match status {
@@ -132,6 +137,11 @@ impl ValueSetKeyInternal {
                     let usage = match usage {
                         KeyUsage::JwsEs256 => DbValueKeyUsage::JwsEs256,
                         KeyUsage::JweA128GCM => DbValueKeyUsage::JweA128GCM,
+                        KeyUsage::ReplTrustAnchor => DbValueKeyUsage::ReplTrustAnchor,
+                        KeyUsage::SshCa => DbValueKeyUsage::SshCa,
+                        KeyUsage::Saml2Signing => DbValueKeyUsage::Saml2Signing,
+                        KeyUsage::Kerberos => DbValueKeyUsage::Kerberos,
+                        KeyUsage::JwsRs256 => DbValueKeyUsage::JwsRs256,
                     };
                     let status_cid = status_cid.into();
                     let status = match status {
@@ -5,7 +5,8 @@ use crate::repl::cid::Cid;
 use crate::schema::SchemaAttribute;
 use crate::server::keys::KeyId;
 use crate::value::{
-    Address, ApiToken, CredentialType, IntentTokenState, Oauth2Session, OauthClaimMapJoin, Session,
+    Address, ApiToken, CredentialFactor, CredentialType, CredentialUsage, IntentTokenState,
+    Oauth2Session, OauthClaimMapJoin, Session,
 };
 use compact_jwt::{crypto::JwsRs256Signer, JwsEs256Signer};
 use dyn_clone::DynClone;
@@ -37,6 +38,7 @@ pub use self::cred::{
     ValueSetAttestedPasskey, ValueSetCredential, ValueSetCredentialType, ValueSetIntentToken,
     ValueSetPasskey, ValueSetWebauthnAttestationCaList,
 };
+pub use self::credusage::ValueSetCredentialUsage;
 pub use self::datetime::ValueSetDateTime;
 pub use self::eckey::ValueSetEcKeyPrivate;
 pub use self::hexstring::ValueSetHexString;
@@ -72,6 +74,7 @@ mod bool;
 mod certificate;
 mod cid;
 mod cred;
+mod credusage;
 mod datetime;
 pub mod eckey;
 mod hexstring;
@@ -400,6 +403,13 @@ pub trait ValueSetT: std::fmt::Debug + DynClone {
         None
     }
 
+    fn as_credential_usage_map(
+        &self,
+    ) -> Option<&BTreeMap<(Uuid, CredentialFactor), CredentialUsage>> {
+        debug_assert!(false);
+        None
+    }
+
     fn to_value_single(&self) -> Option<Value> {
         if self.len() != 1 {
             None
@@ -835,6 +845,7 @@ pub fn from_result_value_iter(
         Value::WebauthnAttestationCaList(_)
         | Value::PhoneNumber(_, _)
         | Value::ApplicationPassword(_)
+        | Value::CredentialUsage(_, _, _)
         | Value::Passkey(_, _, _)
         | Value::AttestedPasskey(_, _, _)
         | Value::TotpSecret(_, _)
@@ -930,6 +941,7 @@ pub fn from_value_iter(mut iter: impl Iterator<Item = Value>) -> Result<ValueSet
             return Err(OperationError::InvalidValueState);
         }
         Value::ApplicationPassword(ap) => ValueSetApplicationPassword::new(ap),
+        Value::CredentialUsage(u, f, c) => ValueSetCredentialUsage::new(u, f, c),
     };
 
     for v in iter {
@@ -991,6 +1003,12 @@ pub fn from_db_valueset_v2(dbvs: DbValueSetV2) -> Result<ValueSet, OperationErro
         DbValueSetV2::HexString(set) => ValueSetHexString::from_dbvs2(set),
         DbValueSetV2::Certificate(set) => ValueSetCertificate::from_dbvs2(set),
         DbValueSetV2::ApplicationPassword(set) => ValueSetApplicationPassword::from_dbvs2(set),
+        DbValueSetV2::CredentialUsage(set) => ValueSetCredentialUsage::from_dbvs2(set),
+        // These must be decrypted by the backend before they are loaded.
+        DbValueSetV2::Encrypted(_) => {
+            error!("An encrypted value was not decrypted by the backend");
+            Err(OperationError::DB0010EncryptedValueInvalid)
+        }
     }
 }
 
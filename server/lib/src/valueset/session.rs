@@ -1,12 +1,14 @@
 use crate::be::dbvalue::{
-    DbCidV1, DbValueAccessScopeV1, DbValueApiToken, DbValueApiTokenScopeV1, DbValueAuthTypeV1,
-    DbValueIdentityId, DbValueOauth2Session, DbValueSession, DbValueSessionStateV1,
+    DbCidV1, DbValueAccessScopeV1, DbValueApiToken, DbValueApiTokenPermissionV1,
+    DbValueApiTokenScopeV1, DbValueAuthTypeV1, DbValueIdentityId, DbValueOauth2Session,
+    DbValueOauth2SessionRevokeReasonV1, DbValueSession, DbValueSessionStateV1,
 };
 use crate::prelude::*;
 use crate::repl::cid::Cid;
 use crate::schema::SchemaAttribute;
 use crate::value::{
-    ApiToken, ApiTokenScope, AuthType, Oauth2Session, Session, SessionScope, SessionState,
+    ApiToken, ApiTokenPermission, ApiTokenScope, AuthType, Oauth2Session,
+    Oauth2SessionRevokeReason, Session, SessionScope, SessionState,
 };
 use crate::valueset::{uuid_to_proto_string, DbValueSetV2, ScimResolveStatus, ValueSet};
 use kanidm_proto::scim_v1::server::ScimApiToken;
@@ -82,6 +84,10 @@ impl ValueSetSession {
                     AuthType::PasswordSecurityKey => DbValueAuthTypeV1::PasswordSecurityKey,
                     AuthType::Passkey => DbValueAuthTypeV1::Passkey,
                     AuthType::AttestedPasskey => DbValueAuthTypeV1::AttestedPasskey,
+                    AuthType::EmailLink => DbValueAuthTypeV1::EmailLink,
+                    AuthType::Federated => DbValueAuthTypeV1::Federated,
+                    AuthType::JwtAssertion => DbValueAuthTypeV1::JwtAssertion,
+                    AuthType::Impersonation => DbValueAuthTypeV1::Impersonation,
                 },
             })
             .collect()
@@ -167,6 +173,10 @@ impl ValueSetSession {
                             DbValueAuthTypeV1::PasswordSecurityKey => AuthType::PasswordSecurityKey,
                             DbValueAuthTypeV1::Passkey => AuthType::Passkey,
                             DbValueAuthTypeV1::AttestedPasskey => AuthType::AttestedPasskey,
+                            DbValueAuthTypeV1::EmailLink => AuthType::EmailLink,
+                            DbValueAuthTypeV1::Federated => AuthType::Federated,
+                            DbValueAuthTypeV1::JwtAssertion => AuthType::JwtAssertion,
+                            DbValueAuthTypeV1::Impersonation => AuthType::Impersonation,
                         };
 
                         Some((
@@ -553,6 +563,7 @@ impl ValueSetOauth2Session {
                                 state,
                                 issued_at,
                                 rs_uuid,
+                                revoke_reason: None,
                             },
                         ))
                     }
@@ -607,6 +618,7 @@ impl ValueSetOauth2Session {
                                 state,
                                 issued_at,
                                 rs_uuid,
+                                revoke_reason: None,
                             },
                         ))
                     } // End V2
@@ -616,6 +628,7 @@ impl ValueSetOauth2Session {
                         state,
                         issued_at,
                         rs_uuid,
+                        revoke_reason,
                     } => {
                         // Convert things.
                         let issued_at = OffsetDateTime::parse(&issued_at, &Rfc3339)
@@ -659,6 +672,7 @@ impl ValueSetOauth2Session {
                                 state,
                                 issued_at,
                                 rs_uuid,
+                                revoke_reason: revoke_reason.map(Oauth2SessionRevokeReason::from),
                             },
                         ))
                     } // End V3
@@ -908,6 +922,9 @@ impl ValueSetT for ValueSetOauth2Session {
                             .expect("Failed to format timestamp as RFC3339")
                     },
                     rs_uuid: m.rs_uuid,
+                    revoke_reason: m
+                        .revoke_reason
+                        .map(DbValueOauth2SessionRevokeReasonV1::from),
                 })
                 .collect(),
         )
@@ -1033,6 +1050,7 @@ impl ValueSetApiToken {
                         issued_at,
                         issued_by,
                         scope,
+                        permissions,
                     } => {
                         // Convert things.
                         let issued_at = OffsetDateTime::parse(&issued_at, &Rfc3339)
@@ -1081,6 +1099,19 @@ impl ValueSetApiToken {
                             DbValueApiTokenScopeV1::Synchronise => ApiTokenScope::Synchronise,
                         };
 
+                        let permissions = permissions
+                            .into_iter()
+                            .map(|p| ApiTokenPermission {
+                                class: p.class,
+                                name_prefix: p.name_prefix,
+                                attrs: p
+                                    .attrs
+                                    .iter()
+                                    .map(|attr| Attribute::from(attr.as_str()))
+                                    .collect(),
+                            })
+                            .collect();
+
                         Some((
                             refer,
                             ApiToken {
@@ -1089,6 +1120,7 @@ impl ValueSetApiToken {
                                 issued_at,
                                 issued_by,
                                 scope,
+                                permissions,
                             },
                         ))
                     }
@@ -1239,6 +1271,19 @@ impl ValueSetT for ValueSetApiToken {
                         ApiTokenScope::ReadWrite => DbValueApiTokenScopeV1::ReadWrite,
                         ApiTokenScope::Synchronise => DbValueApiTokenScopeV1::Synchronise,
                     },
+                    permissions: m
+                        .permissions
+                        .iter()
+                        .map(|p| DbValueApiTokenPermissionV1 {
+                            class: p.class.clone(),
+                            name_prefix: p.name_prefix.clone(),
+                            attrs: p
+                                .attrs
+                                .iter()
+                                .map(|attr| attr.as_str().to_string())
+                                .collect(),
+                        })
+                        .collect(),
                 })
                 .collect(),
         )
@@ -1642,6 +1687,7 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                revoke_reason: None,
             },
         );
 
@@ -1672,6 +1718,7 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                revoke_reason: None,
             },
         );
 
@@ -1682,6 +1729,7 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                revoke_reason: None,
             },
         );
 
@@ -1707,6 +1755,7 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                revoke_reason: None,
             },
         );
 
@@ -1717,6 +1766,7 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                revoke_reason: None,
             },
         );
 
@@ -1745,6 +1795,7 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                revoke_reason: None,
             },
         );
 
@@ -1756,6 +1807,7 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    revoke_reason: None,
                 },
             ),
             (
@@ -1765,6 +1817,7 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    revoke_reason: None,
                 },
             ),
         ])
@@ -1799,6 +1852,7 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                revoke_reason: None,
             },
         );
 
@@ -1810,6 +1864,7 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    revoke_reason: None,
                 },
             ),
             (
@@ -1819,6 +1874,7 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    revoke_reason: None,
                 },
             ),
         ])
@@ -1857,6 +1913,7 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    revoke_reason: None,
                 },
             ),
             (
@@ -1866,6 +1923,7 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    revoke_reason: None,
                 },
             ),
             (
@@ -1875,6 +1933,7 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    revoke_reason: None,
                 },
             ),
         ])
@@ -1934,6 +1993,7 @@ mod tests {
                 issued_at: OffsetDateTime::UNIX_EPOCH,
                 parent: Some(s_uuid),
                 rs_uuid: s_uuid,
+                revoke_reason: None,
             },
         );
 
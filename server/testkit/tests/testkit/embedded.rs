@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use compact_jwt::JwsCompact;
+use kanidm_proto::internal::{Filter, Modify, ModifyList};
+use kanidm_proto::v1::Entry;
+use kanidmd_core::config::Configuration;
+use kanidmd_lib::prelude::{Attribute, EntryClass, QueryServerTransaction, UUID_ADMIN};
+use kanidmd_testkit::{setup_async_test, ADMIN_TEST_PASSWORD, ADMIN_TEST_USER};
+
+#[tokio::test]
+async fn test_embedded_client() {
+    let mut test_env = setup_async_test(Configuration::new_for_test()).await;
+
+    // Without a token, the embedded client is anonymous.
+    let client = test_env.core_handle.client();
+    let whoami = client.whoami().await.expect("Failed to call whoami");
+    assert_eq!(
+        whoami.youare.attrs.get(Attribute::Name.as_str()),
+        Some(&vec!["anonymous".to_string()])
+    );
+
+    assert!(client
+        .delete(Filter::Eq(
+            Attribute::Name.to_string(),
+            "idm_admins".to_string()
+        ))
+        .await
+        .is_err());
+
+    // Use the token from an authenticated https client.
+    test_env
+        .rsclient
+        .auth_simple_password(ADMIN_TEST_USER, ADMIN_TEST_PASSWORD)
+        .await
+        .expect("Failed to authenticate");
+    let token = test_env.rsclient.get_token().await.expect("No token");
+    let token = JwsCompact::from_str(&token).expect("Invalid token");
+
+    let client = test_env.core_handle.client().set_bearer_token(token);
+    let whoami = client.whoami().await.expect("Failed to call whoami");
+    assert_eq!(
+        whoami.youare.attrs.get(Attribute::Spn.as_str()),
+        Some(&vec!["admin@localhost".to_string()])
+    );
+
+    // Create, modify, search and delete an entry.
+    let mut group = Entry::default();
+    group.attrs.insert(
+        Attribute::Class.to_string(),
+        vec![EntryClass::Group.to_string()],
+    );
+    group.attrs.insert(
+        Attribute::Name.to_string(),
+        vec!["embedded_group".to_string()],
+    );
+    client
+        .create(vec![group])
+        .await
+        .expect("Failed to create group");
+
+    let filter = Filter::Eq(Attribute::Name.to_string(), "embedded_group".to_string());
+
+    client
+        .modify(
+            filter.clone(),
+            ModifyList::new_list(vec![Modify::Present(
+                Attribute::Description.to_string(),
+                "An embedded group".to_string(),
+            )]),
+        )
+        .await
+        .expect("Failed to modify group");
+
+    let entries = client
+        .search(filter.clone())
+        .await
+        .expect("Failed to search");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        entries[0].attrs.get(Attribute::Description.as_str()),
+        Some(&vec!["An embedded group".to_string()])
+    );
+
+    // The same entry is visible through https.
+    assert!(test_env
+        .rsclient
+        .idm_group_get("embedded_group")
+        .await
+        .expect("Failed to get group")
+        .is_some());
+
+    client
+        .delete(filter.clone())
+        .await
+        .expect("Failed to delete group");
+
+    assert!(client
+        .search(filter)
+        .await
+        .expect("Failed to search")
+        .is_empty());
+
+    // The query server is available to the host application.
+    let mut qs_read = test_env
+        .core_handle
+        .query_server()
+        .read()
+        .await
+        .expect("Failed to begin read");
+    assert!(qs_read.internal_search_uuid(UUID_ADMIN).is_ok());
+    drop(qs_read);
+
+    test_env.core_handle.shutdown().await;
+}
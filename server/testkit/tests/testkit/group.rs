@@ -1,5 +1,7 @@
+use kanidm_cache::KanidmCache;
 use kanidm_client::{ClientError, KanidmClient, StatusCode};
-use kanidm_proto::constants::ATTR_DESCRIPTION;
+use kanidm_proto::constants::{ATTR_DESCRIPTION, ATTR_MEMBER};
+use kanidm_proto::v1::Entry;
 use kanidmd_testkit::{create_user, ADMIN_TEST_PASSWORD, ADMIN_TEST_USER};
 use serde_json::Value;
 
@@ -51,3 +53,60 @@ async fn test_v1_group_id_attr_post(rsclient: &KanidmClient) {
         ClientError::Http(StatusCode::BAD_REQUEST, _, _)
     ));
 }
+
+#[kanidmd_testkit::test]
+async fn test_v1_group_cache_invalidation(rsclient: &KanidmClient) {
+    let res = rsclient
+        .auth_simple_password(ADMIN_TEST_USER, ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    create_user(rsclient, "foo", "foogroup").await;
+
+    let client = rsclient.new_session().expect("Failed to create client");
+    client
+        .set_token(rsclient.get_token().await.expect("No token"))
+        .await;
+    let cache = KanidmCache::new(client);
+
+    let has_member = |group: &Entry, name: &str| {
+        group.attrs.get(ATTR_MEMBER).is_some_and(|members| {
+            members
+                .iter()
+                .any(|member| member.starts_with(&format!("{}@", name)))
+        })
+    };
+
+    let group = cache
+        .group_get("foogroup")
+        .await
+        .expect("Failed to get group")
+        .expect("Group not found");
+    assert!(has_member(&group, "foo"));
+
+    rsclient
+        .idm_person_account_create("bar", "bar")
+        .await
+        .expect("Failed to create user");
+    rsclient
+        .idm_group_add_members("foogroup", &["bar"])
+        .await
+        .expect("Failed to add member");
+
+    // The change isn't visible until the cache is refreshed.
+    let group = cache
+        .group_get("foogroup")
+        .await
+        .expect("Failed to get group")
+        .expect("Group not found");
+    assert!(!has_member(&group, "bar"));
+
+    cache.refresh().await.expect("Failed to refresh cache");
+
+    let group = cache
+        .group_get("foogroup")
+        .await
+        .expect("Failed to get group")
+        .expect("Group not found");
+    assert!(has_member(&group, "bar"));
+}
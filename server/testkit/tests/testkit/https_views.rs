@@ -0,0 +1,430 @@
+use kanidm_client::KanidmClient;
+use kanidm_proto::constants::{ATTR_DESCRIPTION, ATTR_DISPLAYNAME, ATTR_MAIL, ATTR_MEMBER};
+use kanidmd_testkit::{
+    create_user, login_account, ADMIN_TEST_PASSWORD, ADMIN_TEST_USER, IDM_ADMIN_TEST_PASSWORD,
+    IDM_ADMIN_TEST_USER,
+};
+
+/// Submit a form to an admin view as htmx would, returning the response and the path that the
+/// view pushed to the browser history.
+async fn admin_view_post(
+    rsclient: &KanidmClient,
+    token: &str,
+    path: &str,
+    form: &[(&str, &str)],
+) -> (reqwest::StatusCode, Option<String>, String) {
+    let response = rsclient
+        .client()
+        .post(rsclient.make_url(path))
+        .bearer_auth(token)
+        .header("HX-Request", "true")
+        .form(form)
+        .send()
+        .await
+        .expect("Failed to submit admin view form");
+    let status = response.status();
+    let push_url = response
+        .headers()
+        .get("HX-Push-Url")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await.expect("Failed to read body");
+    (status, push_url, body)
+}
+
+#[kanidmd_testkit::test]
+async fn test_https_views_step_up(rsclient: &KanidmClient) {
+    rsclient
+        .auth_simple_password(ADMIN_TEST_USER, ADMIN_TEST_PASSWORD)
+        .await
+        .expect("Failed to authenticate");
+    let token = rsclient.get_token().await.expect("No session token");
+
+    let client = rsclient.client();
+
+    // The session isn't privileged, so rather than failing we are asked to step up.
+    let response = client
+        .get(rsclient.make_url("/ui/update_credentials"))
+        .bearer_auth(&token)
+        .header("HX-Request", "true")
+        .send()
+        .await
+        .expect("Failed to request credential update view");
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response
+            .headers()
+            .get("HX-Retarget")
+            .and_then(|v| v.to_str().ok()),
+        Some("main")
+    );
+    let body = response.text().await.expect("Failed to read body");
+    assert!(body.contains("/ui/login/step_up"));
+    assert!(body.contains("Reauthenticating as"));
+
+    // Stepping up begins a privileged authentication as the current user.
+    let response = client
+        .post(rsclient.make_url("/ui/login/step_up"))
+        .bearer_auth(&token)
+        .form(&[("return_location", "/ui/update_credentials")])
+        .send()
+        .await
+        .expect("Failed to step up");
+    assert_eq!(response.status(), 200);
+    let body = response.text().await.expect("Failed to read body");
+    assert!(body.contains("/ui/login/pw"));
+
+    // Once authenticated we are returned to the view we started from, which is now usable.
+    let response = client
+        .post(rsclient.make_url("/ui/login/pw"))
+        .form(&[("password", ADMIN_TEST_PASSWORD)])
+        .send()
+        .await
+        .expect("Failed to submit password");
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.url().path(), "/ui/update_credentials");
+    let body = response.text().await.expect("Failed to read body");
+    assert!(!body.contains("/ui/login/step_up"));
+}
+
+#[kanidmd_testkit::test]
+async fn test_https_views_remember_me(rsclient: &KanidmClient) {
+    let client = rsclient.client();
+
+    let response = client
+        .post(rsclient.make_url("/ui/login/begin"))
+        .form(&[("username", ADMIN_TEST_USER), ("remember_me", "1")])
+        .send()
+        .await
+        .expect("Failed to begin login");
+    assert_eq!(response.status(), 200);
+
+    let response = client
+        .post(rsclient.make_url("/ui/login/pw"))
+        .form(&[("password", ADMIN_TEST_PASSWORD)])
+        .send()
+        .await
+        .expect("Failed to submit password");
+    assert_eq!(response.status(), 200);
+
+    let response = client
+        .get(rsclient.make_url("/ui/logout"))
+        .send()
+        .await
+        .expect("Failed to logout");
+    assert_eq!(response.status(), 200);
+
+    // We are remembered, so the username form is skipped.
+    let response = client
+        .get(rsclient.make_url("/ui/login"))
+        .send()
+        .await
+        .expect("Failed to request login view");
+    assert_eq!(response.status(), 200);
+    let body = response.text().await.expect("Failed to read body");
+    assert!(body.contains("/ui/login/pw"));
+    assert!(body.contains("/ui/login/forget"));
+
+    // Once forgotten we are asked for a username again.
+    let response = client
+        .get(rsclient.make_url("/ui/login/forget"))
+        .send()
+        .await
+        .expect("Failed to forget");
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.url().path(), "/ui/login");
+    let body = response.text().await.expect("Failed to read body");
+    assert!(body.contains("/ui/login/begin"));
+    assert!(!body.contains("/ui/login/pw"));
+}
+
+#[kanidmd_testkit::test]
+async fn test_https_views_account_recovery_unavailable(rsclient: &KanidmClient) {
+    let client = rsclient.client();
+
+    // Without an smtp relay recovery is disabled, so login doesn't offer it.
+    let response = client
+        .get(rsclient.make_url("/ui/login"))
+        .send()
+        .await
+        .expect("Failed to request login view");
+    assert_eq!(response.status(), 200);
+    let body = response.text().await.expect("Failed to read body");
+    assert!(!body.contains("/ui/recover"));
+
+    let response = client
+        .get(rsclient.make_url("/ui/recover"))
+        .send()
+        .await
+        .expect("Failed to request recovery view");
+    assert_eq!(response.status(), 200);
+    let body = response.text().await.expect("Failed to read body");
+    assert!(body.contains("Account recovery is not available"));
+
+    let response = client
+        .post(rsclient.make_url("/ui/recover"))
+        .form(&[("username", ADMIN_TEST_USER)])
+        .send()
+        .await
+        .expect("Failed to request recovery code");
+    assert_eq!(response.status(), 200);
+    let body = response.text().await.expect("Failed to read body");
+    assert!(body.contains("Account recovery is not available"));
+}
+
+#[kanidmd_testkit::test]
+async fn test_https_views_admin_person_create_modify(rsclient: &KanidmClient) {
+    rsclient
+        .auth_simple_password(IDM_ADMIN_TEST_USER, IDM_ADMIN_TEST_PASSWORD)
+        .await
+        .expect("Failed to authenticate");
+    let token = rsclient.get_token().await.expect("No session token");
+
+    let response = rsclient
+        .client()
+        .get(rsclient.make_url("/ui/admin/persons/create"))
+        .bearer_auth(&token)
+        .header("HX-Request", "true")
+        .send()
+        .await
+        .expect("Failed to request person create view");
+    assert_eq!(response.status(), 200);
+
+    let (status, push_url, body) = admin_view_post(
+        rsclient,
+        &token,
+        "/ui/admin/persons/create",
+        &[("name", "view_person"), ("displayname", "View Person")],
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(body.contains("View Person"));
+
+    // Once created we are shown the person, and further changes are made at their uuid.
+    let view_path = push_url.expect("No pushed url");
+    let person_path = view_path
+        .strip_suffix("/view")
+        .expect("Pushed url is not a person view");
+    assert!(person_path.starts_with("/ui/admin/person/"));
+
+    let (status, _, body) = admin_view_post(
+        rsclient,
+        &token,
+        &format!("{person_path}/update"),
+        &[
+            ("displayname", "Renamed Person"),
+            ("description", "Made in the admin views"),
+        ],
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Renamed Person"));
+
+    let (status, _, body) = admin_view_post(
+        rsclient,
+        &token,
+        &format!("{person_path}/mail/add"),
+        &[("mail", "view_person@example.com")],
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(body.contains("view_person@example.com"));
+
+    let person = rsclient
+        .idm_person_account_get("view_person")
+        .await
+        .expect("Failed to get person")
+        .expect("Person not found");
+    assert_eq!(
+        person.attrs.get(ATTR_DISPLAYNAME),
+        Some(&vec!["Renamed Person".to_string()])
+    );
+    assert_eq!(
+        person.attrs.get(ATTR_DESCRIPTION),
+        Some(&vec!["Made in the admin views".to_string()])
+    );
+    assert_eq!(
+        person.attrs.get(ATTR_MAIL),
+        Some(&vec!["view_person@example.com".to_string()])
+    );
+
+    // Clearing the description removes it.
+    let (status, _, _) = admin_view_post(
+        rsclient,
+        &token,
+        &format!("{person_path}/update"),
+        &[("displayname", "Renamed Person"), ("description", "")],
+    )
+    .await;
+    assert_eq!(status, 200);
+
+    let (status, _, _) = admin_view_post(
+        rsclient,
+        &token,
+        &format!("{person_path}/mail/remove"),
+        &[("mail", "view_person@example.com")],
+    )
+    .await;
+    assert_eq!(status, 200);
+
+    let person = rsclient
+        .idm_person_account_get("view_person")
+        .await
+        .expect("Failed to get person")
+        .expect("Person not found");
+    assert!(person.attrs.get(ATTR_DESCRIPTION).is_none());
+    assert!(person.attrs.get(ATTR_MAIL).is_none());
+
+    // A person who already exists can't be created again.
+    let (status, _, _) = admin_view_post(
+        rsclient,
+        &token,
+        "/ui/admin/persons/create",
+        &[("name", "view_person"), ("displayname", "View Person")],
+    )
+    .await;
+    assert_ne!(status, 200);
+}
+
+#[kanidmd_testkit::test]
+async fn test_https_views_admin_group_create_modify(rsclient: &KanidmClient) {
+    rsclient
+        .auth_simple_password(IDM_ADMIN_TEST_USER, IDM_ADMIN_TEST_PASSWORD)
+        .await
+        .expect("Failed to authenticate");
+    rsclient
+        .idm_person_account_create("view_member", "View Member")
+        .await
+        .expect("Failed to create person");
+    let token = rsclient.get_token().await.expect("No session token");
+
+    let (status, push_url, body) = admin_view_post(
+        rsclient,
+        &token,
+        "/ui/admin/groups/create",
+        &[
+            ("name", "view_group"),
+            ("description", "Made in the admin views"),
+        ],
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(body.contains("view_group"));
+    assert!(body.contains("This group has no members."));
+
+    let view_path = push_url.expect("No pushed url");
+    let group_path = view_path
+        .strip_suffix("/view")
+        .expect("Pushed url is not a group view");
+    assert!(group_path.starts_with("/ui/admin/group/"));
+
+    let (status, _, body) = admin_view_post(
+        rsclient,
+        &token,
+        &format!("{group_path}/member/add"),
+        &[("member", "view_member")],
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(!body.contains("This group has no members."));
+
+    let has_member = |members: Option<&Vec<String>>| {
+        members.is_some_and(|members| {
+            members
+                .iter()
+                .any(|member| member.starts_with("view_member@"))
+        })
+    };
+
+    let group = rsclient
+        .idm_group_get("view_group")
+        .await
+        .expect("Failed to get group")
+        .expect("Group not found");
+    assert_eq!(
+        group.attrs.get(ATTR_DESCRIPTION),
+        Some(&vec!["Made in the admin views".to_string()])
+    );
+    assert!(has_member(group.attrs.get(ATTR_MEMBER)));
+
+    let (status, _, body) = admin_view_post(
+        rsclient,
+        &token,
+        &format!("{group_path}/member/remove"),
+        &[("member", "view_member")],
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(body.contains("This group has no members."));
+
+    let group = rsclient
+        .idm_group_get("view_group")
+        .await
+        .expect("Failed to get group")
+        .expect("Group not found");
+    assert!(!has_member(group.attrs.get(ATTR_MEMBER)));
+}
+
+#[kanidmd_testkit::test]
+async fn test_https_views_admin_unauthorised(rsclient: &KanidmClient) {
+    rsclient
+        .auth_simple_password(IDM_ADMIN_TEST_USER, IDM_ADMIN_TEST_PASSWORD)
+        .await
+        .expect("Failed to authenticate");
+    create_user(rsclient, "view_not_admin", "view_not_admin_group").await;
+    login_account(rsclient, "view_not_admin").await;
+    let token = rsclient.get_token().await.expect("No session token");
+
+    // The admin views are refused to accounts that aren't idm admins, even when the change
+    // would be permitted by access controls.
+    for path in [
+        "/ui/admin/persons",
+        "/ui/admin/persons/create",
+        "/ui/admin/groups",
+        "/ui/admin/groups/create",
+    ] {
+        let response = rsclient
+            .client()
+            .get(rsclient.make_url(path))
+            .bearer_auth(&token)
+            .header("HX-Request", "true")
+            .send()
+            .await
+            .expect("Failed to request admin view");
+        assert_eq!(response.status(), 403, "{path}");
+    }
+
+    let (status, _, _) = admin_view_post(
+        rsclient,
+        &token,
+        "/ui/admin/persons/create",
+        &[("name", "view_denied"), ("displayname", "View Denied")],
+    )
+    .await;
+    assert_eq!(status, 403);
+
+    let (status, _, _) = admin_view_post(
+        rsclient,
+        &token,
+        "/ui/admin/groups/create",
+        &[("name", "view_denied_group")],
+    )
+    .await;
+    assert_eq!(status, 403);
+
+    let _ = rsclient.logout().await;
+    rsclient
+        .auth_simple_password(IDM_ADMIN_TEST_USER, IDM_ADMIN_TEST_PASSWORD)
+        .await
+        .expect("Failed to authenticate");
+    assert!(rsclient
+        .idm_person_account_get("view_denied")
+        .await
+        .expect("Failed to get person")
+        .is_none());
+    assert!(rsclient
+        .idm_group_get("view_denied_group")
+        .await
+        .expect("Failed to get group")
+        .is_none());
+}
@@ -1,9 +1,11 @@
 mod apidocs;
 mod domain;
+mod embedded;
 mod group;
 mod http_manifest;
 mod https_extractors;
 mod https_middleware;
+mod https_views;
 mod identity_verification_tests;
 mod integration;
 mod ldap_basic;
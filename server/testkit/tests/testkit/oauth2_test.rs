@@ -21,10 +21,10 @@ use url::{form_urlencoded::parse as query_parse, Url};
 
 use kanidm_client::{http::header, KanidmClient, StatusCode};
 use kanidmd_testkit::{
-    assert_no_cache, ADMIN_TEST_PASSWORD, ADMIN_TEST_USER, NOT_ADMIN_TEST_EMAIL,
-    NOT_ADMIN_TEST_PASSWORD, NOT_ADMIN_TEST_USERNAME, TEST_INTEGRATION_RS_DISPLAY,
-    TEST_INTEGRATION_RS_GROUP_ALL, TEST_INTEGRATION_RS_ID, TEST_INTEGRATION_RS_REDIRECT_URL,
-    TEST_INTEGRATION_RS_URL,
+    assert_no_cache, create_user, login_account, login_put_admin_idm_admins, ADMIN_TEST_PASSWORD,
+    ADMIN_TEST_USER, NOT_ADMIN_TEST_EMAIL, NOT_ADMIN_TEST_PASSWORD, NOT_ADMIN_TEST_USERNAME,
+    TEST_INTEGRATION_RS_DISPLAY, TEST_INTEGRATION_RS_GROUP_ALL, TEST_INTEGRATION_RS_ID,
+    TEST_INTEGRATION_RS_REDIRECT_URL, TEST_INTEGRATION_RS_URL,
 };
 
 /// Tests an OAuth 2.0 / OpenID confidential client Authorisation Client flow.
@@ -1018,3 +1018,75 @@ async fn test_oauth2_token_revoke_post(rsclient: &KanidmClient) {
     println!("{:?}", response);
     assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
 }
+
+#[kanidmd_testkit::test]
+async fn test_oauth2_entry_managed_by(rsclient: &KanidmClient) {
+    login_put_admin_idm_admins(rsclient).await;
+
+    rsclient
+        .idm_oauth2_rs_basic_create(
+            TEST_INTEGRATION_RS_ID,
+            TEST_INTEGRATION_RS_DISPLAY,
+            TEST_INTEGRATION_RS_URL,
+        )
+        .await
+        .expect("Failed to create oauth2 config");
+
+    create_user(rsclient, "app_owner", "app_owners").await;
+
+    rsclient
+        .idm_oauth2_rs_set_entry_managed_by(TEST_INTEGRATION_RS_ID, "app_owners")
+        .await
+        .expect("Failed to set entry manager");
+
+    login_account(rsclient, "app_owner").await;
+
+    // The entry manager can read the client, and manage who has access to it.
+    let rs = rsclient
+        .idm_oauth2_rs_get(TEST_INTEGRATION_RS_ID)
+        .await
+        .expect("Failed to read oauth2 config")
+        .expect("No oauth2 config found");
+    assert!(rs.attrs.contains_key(ATTR_ENTRY_MANAGED_BY));
+
+    rsclient
+        .idm_oauth2_rs_update(
+            TEST_INTEGRATION_RS_ID,
+            None,
+            Some("Managed Integration"),
+            None,
+            false,
+            false,
+            false,
+        )
+        .await
+        .expect("Failed to update displayname as entry manager");
+
+    rsclient
+        .idm_oauth2_rs_update_scope_map(
+            TEST_INTEGRATION_RS_ID,
+            "app_owners",
+            vec![OAUTH2_SCOPE_OPENID],
+        )
+        .await
+        .expect("Failed to update scope map as entry manager");
+
+    // But it can't rename the client or weaken its security settings.
+    assert!(rsclient
+        .idm_oauth2_rs_update(
+            TEST_INTEGRATION_RS_ID,
+            Some("renamed_integration"),
+            None,
+            None,
+            false,
+            false,
+            false,
+        )
+        .await
+        .is_err());
+
+    assert!(rsclient
+        .idm_oauth2_rs_disable_pkce(TEST_INTEGRATION_RS_ID)
+        .await
+        .is_err());
+}
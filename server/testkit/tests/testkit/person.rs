@@ -1,6 +1,8 @@
 use kanidm_client::{ClientError, KanidmClient, StatusCode};
-use kanidm_proto::constants::ATTR_MAIL;
-use kanidmd_testkit::{create_user, ADMIN_TEST_PASSWORD, ADMIN_TEST_USER};
+use kanidm_proto::constants::{ATTR_MAIL, ATTR_PASSWORD_IMPORT};
+use kanidmd_testkit::{
+    create_user, ADMIN_TEST_PASSWORD, ADMIN_TEST_USER, IDM_ADMIN_TEST_PASSWORD, IDM_ADMIN_TEST_USER,
+};
 use serde_json::Value;
 
 #[kanidmd_testkit::test]
@@ -51,3 +53,36 @@ async fn test_v1_person_id_ssh_pubkeys_post(rsclient: &KanidmClient) {
         ClientError::Http(StatusCode::BAD_REQUEST, _, _)
     ));
 }
+
+#[kanidmd_testkit::test]
+async fn test_v1_person_password_import_bcrypt(rsclient: &KanidmClient) {
+    let res = rsclient
+        .auth_simple_password(IDM_ADMIN_TEST_USER, IDM_ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    rsclient
+        .idm_person_account_create("imported", "Imported Person")
+        .await
+        .expect("Failed to create person");
+
+    rsclient
+        .idm_person_account_add_attr(
+            "imported",
+            ATTR_PASSWORD_IMPORT,
+            &["$2b$04$abcdefghijklmnopqrstuughE8Ev8uGFaUgY2cNEySvxngrb/Jzdm"],
+        )
+        .await
+        .expect("Failed to import password hash");
+
+    // The imported hash is upgraded on the first authentication, so both must succeed.
+    for _ in 0..2 {
+        let client = rsclient.new_session().expect("Failed to create client");
+        let res = client.auth_simple_password("imported", "password").await;
+        assert!(res.is_ok());
+    }
+
+    let client = rsclient.new_session().expect("Failed to create client");
+    let res = client.auth_simple_password("imported", "password1").await;
+    assert!(res.is_err());
+}
@@ -1,6 +1,10 @@
 use compact_jwt::{traits::JwsVerifiable, JwsCompact, JwsEs256Verifier, JwsVerifier};
 use kanidm_client::KanidmClient;
 use kanidm_proto::internal::ScimSyncToken;
+use kanidm_proto::scim_v1::provision::{
+    ScimPatchRequest, ScimProvisionGroup, ScimProvisionGroupListResponse, ScimProvisionMember,
+    ScimProvisionUser,
+};
 use kanidm_proto::scim_v1::ScimEntryGetQuery;
 use kanidmd_lib::constants::NAME_IDM_ADMINS;
 use kanidmd_lib::prelude::Attribute;
@@ -195,3 +199,107 @@ async fn test_scim_sync_entry_get(rsclient: &KanidmClient) {
     assert!(!scim_entry.attrs.contains_key(&Attribute::Class));
     assert!(scim_entry.attrs.contains_key(&Attribute::Name));
 }
+
+#[kanidmd_testkit::test]
+async fn test_scim_provision_lifecycle(rsclient: &KanidmClient) {
+    let res = rsclient
+        .auth_simple_password("admin", ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    // All admin to create persons and groups.
+    rsclient
+        .idm_group_add_members(NAME_IDM_ADMINS, &["admin"])
+        .await
+        .unwrap();
+
+    let token = rsclient.get_token().await.expect("No token");
+    let client = rsclient.client();
+
+    let mut user = ScimProvisionUser::new("scim_provisioned".to_string());
+    user.display_name = Some("Provisioned Person".to_string());
+
+    let res = client
+        .post(rsclient.make_url("/scim/v2/Users"))
+        .bearer_auth(&token)
+        .json(&user)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::CREATED);
+    let user: ScimProvisionUser = res.json().await.unwrap();
+    let user_uuid = user.id.expect("No id returned");
+
+    // Creating the same user again conflicts.
+    let res = client
+        .post(rsclient.make_url("/scim/v2/Users"))
+        .bearer_auth(&token)
+        .json(&user)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::CONFLICT);
+
+    let mut group = ScimProvisionGroup::new("scim_provisioned_group".to_string());
+    group.members = vec![ScimProvisionMember {
+        value: user_uuid,
+        display: None,
+    }];
+
+    let res = client
+        .post(rsclient.make_url("/scim/v2/Groups"))
+        .bearer_auth(&token)
+        .json(&group)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::CREATED);
+
+    let res = client
+        .get(rsclient.make_url("/scim/v2/Groups"))
+        .query(&[("filter", "displayName eq \"scim_provisioned_group\"")])
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let list: ScimProvisionGroupListResponse = res.json().await.unwrap();
+    assert_eq!(list.total_results, 1);
+    assert_eq!(list.resources[0].members.len(), 1);
+
+    // Deactivate the user.
+    let patch: ScimPatchRequest = serde_json::from_str(
+        r#"{
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+            "Operations": [{ "op": "replace", "path": "active", "value": false }]
+        }"#,
+    )
+    .unwrap();
+
+    let res = client
+        .patch(rsclient.make_url(&format!("/scim/v2/Users/{}", user_uuid)))
+        .bearer_auth(&token)
+        .json(&patch)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let user: ScimProvisionUser = res.json().await.unwrap();
+    assert_eq!(user.active, Some(false));
+
+    let res = client
+        .delete(rsclient.make_url(&format!("/scim/v2/Users/{}", user_uuid)))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NO_CONTENT);
+
+    let res = client
+        .get(rsclient.make_url(&format!("/scim/v2/Users/{}", user_uuid)))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+}
@@ -1,4 +1,5 @@
 use kanidm_client::KanidmClient;
+use kanidm_proto::internal::{HealthReport, SelfTestCheck, SelfTestStatus};
 
 /// This literally tests that the thing exists and responds in a way we expect, probably worth testing it better...
 #[kanidmd_testkit::test]
@@ -26,3 +27,34 @@ async fn test_v1_system_post_attr(rsclient: &KanidmClient) {
     let body = response.text().await.unwrap();
     eprintln!("{}", body);
 }
+
+#[kanidmd_testkit::test]
+async fn test_status_live_and_ready(rsclient: &KanidmClient) {
+    let client = rsclient.client();
+
+    let response = client
+        .get(rsclient.make_url("/status/live"))
+        .send()
+        .await
+        .expect("Failed to query /status/live");
+    assert_eq!(response.status(), 200);
+    let report: HealthReport = response.json().await.unwrap();
+    assert_eq!(report.status, SelfTestStatus::Pass);
+
+    let response = client
+        .get(rsclient.make_url("/status/ready"))
+        .send()
+        .await
+        .expect("Failed to query /status/ready");
+    assert_eq!(response.status(), 200);
+    let report: HealthReport = response.json().await.unwrap();
+    eprintln!("{:?}", report);
+    assert!(report
+        .checks
+        .iter()
+        .any(|item| item.check == SelfTestCheck::Database && item.status == SelfTestStatus::Pass));
+    assert!(report
+        .checks
+        .iter()
+        .any(|item| item.check == SelfTestCheck::KeyProvider));
+}
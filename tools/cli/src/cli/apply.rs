@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use kanidm_proto::v1::{DeclarativeState, StateChange};
+use serde::Serialize;
+
+use crate::common::OpType;
+use crate::{handle_client_error, ApplyOpt, OutputMode};
+
+#[derive(Debug, Serialize)]
+struct ApplySummary<'a> {
+    dry_run: bool,
+    applied: &'a [StateChange],
+    pending: &'a [StateChange],
+}
+
+impl ApplyOpt {
+    pub fn debug(&self) -> bool {
+        self.commonopts.debug
+    }
+
+    fn read_state(&self) -> Result<DeclarativeState, String> {
+        let f = File::open(&self.file)
+            .map_err(|e| format!("Unable to open {}: {:?}", self.file.display(), e))?;
+        serde_json::from_reader(BufReader::new(f))
+            .map_err(|e| format!("Invalid state {}: {}", self.file.display(), e))
+    }
+
+    pub async fn exec(&self) {
+        let state = match self.read_state() {
+            Ok(state) => state,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+
+        let optype = if self.dry_run {
+            OpType::Read
+        } else {
+            OpType::Write
+        };
+        let client = self.commonopts.to_client(optype).await;
+
+        let changes = match client.state_plan(&state).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                handle_client_error(e, self.commonopts.output_mode);
+                return;
+            }
+        };
+
+        let mut applied = 0;
+
+        if !self.dry_run {
+            for change in changes.iter() {
+                if let Err(e) = client.state_apply_change(change).await {
+                    error!("Failed to {}", change);
+                    handle_client_error(e, self.commonopts.output_mode);
+                    break;
+                }
+                applied += 1;
+            }
+        }
+
+        let (applied, pending) = changes.split_at(applied);
+
+        match self.commonopts.output_mode {
+            OutputMode::Json => {
+                let summary = ApplySummary {
+                    dry_run: self.dry_run,
+                    applied,
+                    pending,
+                };
+                #[allow(clippy::expect_used)]
+                let json = serde_json::to_string(&summary).expect("Failed to serialise json");
+                println!("{}", json);
+            }
+            OutputMode::Text => {
+                if changes.is_empty() {
+                    println!("The server already matches {}", self.file.display());
+                    return;
+                }
+                for change in applied {
+                    println!("applied: {}", change);
+                }
+                for change in pending {
+                    println!("pending: {}", change);
+                }
+            }
+        }
+    }
+}
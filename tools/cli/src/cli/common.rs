@@ -1,4 +1,5 @@
 use std::env;
+use std::time::Duration;
 
 use compact_jwt::{traits::JwsVerifiable, JwsCompact, JwsEs256Verifier, JwsVerifier, JwtError};
 use dialoguer::theme::ColorfulTheme;
@@ -423,6 +424,62 @@ pub fn prompt_for_username_get_token() -> Result<String, String> {
 }
 */
 
+/// This parses a duration such as `8h`, `30m` or `1d12h`, made of numbers that are each
+/// followed by a unit of `s`, `m`, `h`, `d` or `w`.
+pub(crate) fn try_duration_from_string(input: &str) -> Result<Duration, ()> {
+    let mut total: u64 = 0;
+    let mut value: Option<u64> = None;
+
+    for c in input.trim().chars() {
+        if let Some(digit) = c.to_digit(10) {
+            value = value
+                .unwrap_or_default()
+                .checked_mul(10)
+                .and_then(|v| v.checked_add(digit as u64));
+            if value.is_none() {
+                error!("Duration {} is too large", input);
+                return Err(());
+            }
+            continue;
+        }
+
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => {
+                error!("Invalid unit {:?} in duration {}", c, input);
+                return Err(());
+            }
+        };
+
+        let Some(v) = value.take() else {
+            error!("Missing value before unit {:?} in duration {}", c, input);
+            return Err(());
+        };
+
+        total = match v.checked_mul(unit).and_then(|v| total.checked_add(v)) {
+            Some(total) => total,
+            None => {
+                error!("Duration {} is too large", input);
+                return Err(());
+            }
+        };
+    }
+
+    if value.is_some() || total == 0 {
+        error!(
+            "Invalid duration {} - each value must have a unit of s, m, h, d or w",
+            input
+        );
+        return Err(());
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
 /// This parses the input for the person/service-account expire-at CLI commands
 ///
 /// If it fails, return error, if it needs to *clear* the result, return Ok(None),
@@ -456,3 +513,30 @@ pub(crate) fn try_expire_at_from_string(input: &str) -> Result<Option<String>, (
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::try_duration_from_string;
+    use std::time::Duration;
+
+    #[test]
+    fn test_try_duration_from_string() {
+        assert_eq!(
+            try_duration_from_string("8h"),
+            Ok(Duration::from_secs(8 * 3600))
+        );
+        assert_eq!(
+            try_duration_from_string("30m"),
+            Ok(Duration::from_secs(1800))
+        );
+        assert_eq!(
+            try_duration_from_string("1d12h"),
+            Ok(Duration::from_secs(86400 + 12 * 3600))
+        );
+        assert!(try_duration_from_string("").is_err());
+        assert!(try_duration_from_string("8").is_err());
+        assert!(try_duration_from_string("h").is_err());
+        assert!(try_duration_from_string("8y").is_err());
+        assert!(try_duration_from_string("0h").is_err());
+    }
+}
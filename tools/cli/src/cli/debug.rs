@@ -0,0 +1,489 @@
+use crate::common::OpType;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use kanidm_proto::constants::{ATTR_CREATED_AT_CID, ATTR_LAST_MODIFIED_CID, ATTR_UUID};
+use kanidm_proto::internal::{EntryHistoryResponse, EntryInspectResponse, Filter};
+use kanidm_proto::v1::Entry;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{handle_client_error, CommonOpt, DebugOpt, EntryDiffOpt, OutputMode};
+
+/// A saved entry may be a single entry, or a list of entries such as from `kanidm raw search`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedEntries {
+    Single(Entry),
+    List(Vec<Entry>),
+}
+
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+struct AttrDiff {
+    attr: String,
+    left_only: Vec<String>,
+    right_only: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct EntryDiff {
+    left: String,
+    right: String,
+    left_present: bool,
+    right_present: bool,
+    metadata: Vec<AttrDiff>,
+    attrs: Vec<AttrDiff>,
+}
+
+/// The change metadata attributes, which are shown separately to the entry content.
+const METADATA_ATTRS: [&str; 2] = [ATTR_CREATED_AT_CID, ATTR_LAST_MODIFIED_CID];
+
+fn diff_attrs(
+    left: &BTreeMap<String, Vec<String>>,
+    right: &BTreeMap<String, Vec<String>>,
+) -> Vec<AttrDiff> {
+    let attrs: BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+
+    attrs
+        .into_iter()
+        .filter_map(|attr| {
+            let l_vals: BTreeSet<&String> = left.get(attr).into_iter().flatten().collect();
+            let r_vals: BTreeSet<&String> = right.get(attr).into_iter().flatten().collect();
+
+            let diff = AttrDiff {
+                attr: attr.clone(),
+                left_only: l_vals.difference(&r_vals).map(|v| v.to_string()).collect(),
+                right_only: r_vals.difference(&l_vals).map(|v| v.to_string()).collect(),
+            };
+
+            if diff.left_only.is_empty() && diff.right_only.is_empty() {
+                None
+            } else {
+                Some(diff)
+            }
+        })
+        .collect()
+}
+
+fn diff_entries(
+    left_label: String,
+    left: Option<&Entry>,
+    right_label: String,
+    right: Option<&Entry>,
+) -> EntryDiff {
+    let empty = BTreeMap::default();
+    let l_attrs = left.map(|e| &e.attrs).unwrap_or(&empty);
+    let r_attrs = right.map(|e| &e.attrs).unwrap_or(&empty);
+
+    let (metadata, attrs) = diff_attrs(l_attrs, r_attrs)
+        .into_iter()
+        .partition(|d| METADATA_ATTRS.contains(&d.attr.as_str()));
+
+    EntryDiff {
+        left: left_label,
+        right: right_label,
+        left_present: left.is_some(),
+        right_present: right.is_some(),
+        metadata,
+        attrs,
+    }
+}
+
+fn display_diff(diff: &EntryDiff) {
+    println!("--- {}", diff.left);
+    println!("+++ {}", diff.right);
+
+    if !diff.left_present {
+        println!("entry is not present in {}", diff.left);
+    }
+    if !diff.right_present {
+        println!("entry is not present in {}", diff.right);
+    }
+
+    if diff.metadata.is_empty() && diff.attrs.is_empty() {
+        println!("entries are identical");
+        return;
+    }
+
+    if !diff.metadata.is_empty() {
+        println!("change metadata:");
+        for d in diff.metadata.iter() {
+            println!("  {}:", d.attr);
+            d.left_only.iter().for_each(|v| println!("  - {}", v));
+            d.right_only.iter().for_each(|v| println!("  + {}", v));
+        }
+
+        // Cids are ordered by their timestamp, so the greater change is the most recent.
+        if let Some(d) = diff
+            .metadata
+            .iter()
+            .find(|d| d.attr == ATTR_LAST_MODIFIED_CID)
+        {
+            match (d.left_only.iter().max(), d.right_only.iter().max()) {
+                (Some(l), Some(r)) if l > r => {
+                    println!("  {} has the most recent change", diff.left)
+                }
+                (Some(_), Some(_)) => println!("  {} has the most recent change", diff.right),
+                _ => {}
+            }
+        }
+    }
+
+    if !diff.attrs.is_empty() {
+        println!("attributes:");
+        for d in diff.attrs.iter() {
+            println!("  {}:", d.attr);
+            d.left_only.iter().for_each(|v| println!("  - {}", v));
+            d.right_only.iter().for_each(|v| println!("  + {}", v));
+        }
+    }
+}
+
+fn output_diff(diff: &EntryDiff, output_mode: OutputMode) {
+    match output_mode {
+        OutputMode::Json => println!(
+            "{}",
+            serde_json::to_string(diff).expect("Failed to serialise json")
+        ),
+        OutputMode::Text => display_diff(diff),
+    }
+}
+
+fn read_saved_entry<P: AsRef<Path>>(
+    path: P,
+    uuid: Option<Uuid>,
+) -> Result<Option<Entry>, Box<dyn Error>> {
+    let f = File::open(path)?;
+    let r = BufReader::new(f);
+
+    let mut entries = match serde_json::from_reader(r)? {
+        SavedEntries::Single(e) => vec![e],
+        SavedEntries::List(l) => l,
+    };
+
+    match uuid {
+        Some(uuid) => {
+            let uuid = uuid.to_string();
+            Ok(entries.into_iter().find(|e| {
+                e.attrs
+                    .get(ATTR_UUID)
+                    .map(|vs| vs.contains(&uuid))
+                    .unwrap_or(false)
+            }))
+        }
+        None if entries.len() <= 1 => Ok(entries.pop()),
+        None => Err("multiple entries found, select the entry to compare with --uuid".into()),
+    }
+}
+
+async fn fetch_entry(copt: &CommonOpt, uuid: Uuid) -> Option<Option<Entry>> {
+    let client = copt.to_client(OpType::Read).await;
+    let filter = Filter::Eq(ATTR_UUID.to_string(), uuid.to_string());
+    match client.search(filter).await {
+        Ok(mut entries) => Some(entries.pop()),
+        Err(e) => {
+            error!("Error searching {} -> {:?}", client.get_url(), e);
+            None
+        }
+    }
+}
+
+fn display_inspect(inspect: &EntryInspectResponse) {
+    println!("uuid: {}", inspect.uuid);
+    println!("state: {:?} at {}", inspect.state, inspect.state_at);
+    if let Some(sync_parent_uuid) = inspect.sync_parent_uuid {
+        println!("sync parent: {}", sync_parent_uuid);
+    }
+    println!("attributes:");
+    for (attr, a) in inspect.attrs.iter() {
+        match &a.changed_at {
+            Some(cid) => println!("  {} (changed at {}):", attr, cid),
+            None => println!("  {}:", attr),
+        }
+        a.values.iter().for_each(|v| println!("    {}", v));
+    }
+}
+
+/// The time of a change, from its change id. Change ids are formatted as the nanoseconds
+/// since the epoch followed by the uuid of the server that made the change.
+pub(crate) fn cid_time(cid: &str) -> Option<OffsetDateTime> {
+    let (ts, _) = cid.split_once('-')?;
+    let ts = ts.parse::<i128>().ok()?;
+    OffsetDateTime::from_unix_timestamp_nanos(ts).ok()
+}
+
+/// The identity that last changed each attribute, from the most recent modification in the
+/// history that included the attribute. Attributes that have not been modified since the
+/// entry was created are attributed to the creator, if that is still in the history.
+pub(crate) fn last_changed_by(history: &EntryHistoryResponse) -> BTreeMap<&str, Uuid> {
+    let mut changed_by = BTreeMap::new();
+
+    for event in history.events.iter() {
+        let Some(actor) = event.actor else {
+            continue;
+        };
+        if event.event == "entries_created" {
+            for attr in history.entry.attrs.keys() {
+                changed_by.insert(attr.as_str(), actor);
+            }
+        } else {
+            for attr in event.attrs.iter() {
+                changed_by.insert(attr.as_str(), actor);
+            }
+        }
+    }
+
+    changed_by
+}
+
+fn display_history(history: &EntryHistoryResponse) {
+    display_inspect(&history.entry);
+
+    let changed_by = last_changed_by(history);
+    if !changed_by.is_empty() {
+        println!("last changed by:");
+        for (attr, actor) in changed_by.iter() {
+            println!("  {}: {}", attr, actor);
+        }
+    }
+
+    println!("history:");
+    if history.events.is_empty() {
+        println!("  no changes to this entry are held in the audit log");
+    }
+    for event in history.events.iter() {
+        let actor = event
+            .actor
+            .map(|actor| format!(" by {}", actor))
+            .unwrap_or_default();
+        if event.attrs.is_empty() {
+            println!("  {} {}{}", event.time, event.event, actor);
+        } else {
+            println!(
+                "  {} {}{}: {}",
+                event.time,
+                event.event,
+                actor,
+                event.attrs.join(", ")
+            );
+        }
+    }
+}
+
+impl DebugOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            DebugOpt::EntryDiff { commands } => match commands {
+                EntryDiffOpt::Replica { commonopts, .. }
+                | EntryDiffOpt::Files { commonopts, .. } => commonopts.debug,
+            },
+            DebugOpt::EntryInspect { commonopts, .. }
+            | DebugOpt::EntryHistory { commonopts, .. } => commonopts.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            DebugOpt::EntryDiff { commands } => match commands {
+                EntryDiffOpt::Replica {
+                    uuid,
+                    other_url,
+                    commonopts,
+                } => {
+                    let mut other_copt = commonopts.clone();
+                    other_copt.addr = Some(other_url.clone());
+
+                    let left_label = commonopts.to_unauth_client().get_url().to_string();
+
+                    let Some(left) = fetch_entry(commonopts, *uuid).await else {
+                        return;
+                    };
+                    let Some(right) = fetch_entry(&other_copt, *uuid).await else {
+                        return;
+                    };
+
+                    let diff =
+                        diff_entries(left_label, left.as_ref(), other_url.clone(), right.as_ref());
+                    output_diff(&diff, commonopts.output_mode);
+                }
+                EntryDiffOpt::Files {
+                    left,
+                    right,
+                    uuid,
+                    commonopts,
+                } => {
+                    let l_entry = match read_saved_entry(left, *uuid) {
+                        Ok(e) => e,
+                        Err(e) => {
+                            error!("Error reading {} -> {}", left.display(), e);
+                            return;
+                        }
+                    };
+                    let r_entry = match read_saved_entry(right, *uuid) {
+                        Ok(e) => e,
+                        Err(e) => {
+                            error!("Error reading {} -> {}", right.display(), e);
+                            return;
+                        }
+                    };
+
+                    let diff = diff_entries(
+                        left.display().to_string(),
+                        l_entry.as_ref(),
+                        right.display().to_string(),
+                        r_entry.as_ref(),
+                    );
+                    output_diff(&diff, commonopts.output_mode);
+                }
+            },
+            DebugOpt::EntryInspect { id, commonopts } => {
+                let client = commonopts.to_client(OpType::Read).await;
+                match client.entry_inspect(id.as_str()).await {
+                    Ok(inspect) => match commonopts.output_mode {
+                        OutputMode::Json => println!(
+                            "{}",
+                            serde_json::to_string(&inspect).expect("Failed to serialise json")
+                        ),
+                        OutputMode::Text => display_inspect(&inspect),
+                    },
+                    Err(e) => handle_client_error(e, commonopts.output_mode),
+                }
+            }
+            DebugOpt::EntryHistory { id, commonopts } => {
+                let client = commonopts.to_client(OpType::Read).await;
+                match client.entry_history(id.as_str()).await {
+                    Ok(history) => match commonopts.output_mode {
+                        OutputMode::Json => println!(
+                            "{}",
+                            serde_json::to_string(&history).expect("Failed to serialise json")
+                        ),
+                        OutputMode::Text => display_history(&history),
+                    },
+                    Err(e) => handle_client_error(e, commonopts.output_mode),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cid_time, diff_entries, last_changed_by, AttrDiff};
+    use kanidm_proto::internal::{
+        EntryHistoryEvent, EntryHistoryResponse, EntryInspectAttr, EntryInspectResponse,
+        EntryInspectState,
+    };
+    use kanidm_proto::v1::Entry;
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+
+    fn entry(attrs: &[(&str, &[&str])]) -> Entry {
+        Entry {
+            attrs: attrs
+                .iter()
+                .map(|(k, vs)| (k.to_string(), vs.iter().map(|v| v.to_string()).collect()))
+                .collect::<BTreeMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_entry_diff() {
+        let left = entry(&[
+            ("name", &["testgroup"]),
+            ("member", &["alice", "bob"]),
+            ("last_modified_cid", &["0001-a"]),
+        ]);
+        let right = entry(&[
+            ("name", &["testgroup"]),
+            ("member", &["bob", "carol"]),
+            ("description", &["a group"]),
+            ("last_modified_cid", &["0002-b"]),
+        ]);
+
+        let diff = diff_entries(
+            "left".to_string(),
+            Some(&left),
+            "right".to_string(),
+            Some(&right),
+        );
+
+        assert_eq!(
+            diff.metadata,
+            vec![AttrDiff {
+                attr: "last_modified_cid".to_string(),
+                left_only: vec!["0001-a".to_string()],
+                right_only: vec!["0002-b".to_string()],
+            }]
+        );
+        assert_eq!(
+            diff.attrs,
+            vec![
+                AttrDiff {
+                    attr: "description".to_string(),
+                    left_only: vec![],
+                    right_only: vec!["a group".to_string()],
+                },
+                AttrDiff {
+                    attr: "member".to_string(),
+                    left_only: vec!["alice".to_string()],
+                    right_only: vec!["carol".to_string()],
+                },
+            ]
+        );
+
+        // An entry missing from one side shows all of its attributes.
+        let diff = diff_entries("left".to_string(), Some(&left), "right".to_string(), None);
+        assert!(!diff.right_present);
+        assert_eq!(diff.attrs.len(), 2);
+        assert_eq!(diff.metadata.len(), 1);
+    }
+
+    #[test]
+    fn test_cid_time() {
+        let t = cid_time("00000000000001700000000000000000-00000000-0000-0000-0000-000000000000")
+            .expect("Invalid cid");
+        assert_eq!(t.unix_timestamp(), 1700000000);
+        assert!(cid_time("invalid").is_none());
+    }
+
+    #[test]
+    fn test_last_changed_by() {
+        let creator = Uuid::new_v4();
+        let modifier = Uuid::new_v4();
+        let attr = |values: &[&str]| EntryInspectAttr {
+            values: values.iter().map(|v| v.to_string()).collect(),
+            changed_at: None,
+        };
+        let event = |event: &str, actor: Uuid, attrs: &[&str]| EntryHistoryEvent {
+            time: "2024-01-01T00:00:00Z".to_string(),
+            event: event.to_string(),
+            actor: Some(actor),
+            attrs: attrs.iter().map(|a| a.to_string()).collect(),
+        };
+
+        let history = EntryHistoryResponse {
+            entry: EntryInspectResponse {
+                uuid: Uuid::new_v4(),
+                state: EntryInspectState::Recycled,
+                state_at: "0001-a".to_string(),
+                sync_parent_uuid: None,
+                attrs: BTreeMap::from([
+                    ("name".to_string(), attr(&["testgroup"])),
+                    ("description".to_string(), attr(&["a group"])),
+                ]),
+            },
+            events: vec![
+                event("entries_created", creator, &[]),
+                event("entries_modified", modifier, &["description"]),
+            ],
+        };
+
+        let changed_by = last_changed_by(&history);
+        assert_eq!(changed_by.get("name"), Some(&creator));
+        assert_eq!(changed_by.get("description"), Some(&modifier));
+    }
+}
@@ -12,7 +12,17 @@ impl DomainOpt {
             | DomainOpt::SetImage { copt, .. }
             | DomainOpt::RemoveImage { copt }
             | DomainOpt::SetLdapAllowUnixPasswordBind { copt, .. }
+            | DomainOpt::SetLdapGroupCompat { copt, .. }
             | DomainOpt::SetAllowEasterEggs { copt, .. }
+            | DomainOpt::SetTheme { copt, .. }
+            | DomainOpt::SetLoginBanner { copt, .. }
+            | DomainOpt::RemoveLoginBanner { copt }
+            | DomainOpt::SetPrimaryColour { copt, .. }
+            | DomainOpt::RemovePrimaryColour { copt }
+            | DomainOpt::SetWhitePagesTrustedNetworks { copt, .. }
+            | DomainOpt::RemoveWhitePagesTrustedNetworks { copt }
+            | DomainOpt::SetWhitePagesRateLimit { copt, .. }
+            | DomainOpt::RemoveWhitePagesRateLimit { copt }
             | DomainOpt::RevokeKey { copt, .. }
             | DomainOpt::Show(copt)
             | DomainOpt::SetLdapMaxQueryableAttrs { copt, .. } => copt.debug,
@@ -70,6 +80,13 @@ impl DomainOpt {
                     Err(e) => handle_client_error(e, copt.output_mode),
                 }
             }
+            DomainOpt::SetLdapGroupCompat { copt, enable } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_domain_set_ldap_group_compat(*enable).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
             DomainOpt::SetAllowEasterEggs { copt, enable } => {
                 let client = copt.to_client(OpType::Write).await;
                 match client.idm_set_domain_allow_easter_eggs(*enable).await {
@@ -83,6 +100,78 @@ impl DomainOpt {
                     Err(e) => handle_client_error(e, copt.output_mode),
                 }
             }
+            DomainOpt::SetTheme { copt, theme } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_domain_set_theme(*theme).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            DomainOpt::SetLoginBanner { copt, banner } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_domain_set_login_banner(banner).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            DomainOpt::RemoveLoginBanner { copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_domain_remove_login_banner().await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            DomainOpt::SetPrimaryColour { copt, colour } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_domain_set_primary_colour(colour).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            DomainOpt::RemovePrimaryColour { copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_domain_remove_primary_colour().await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            DomainOpt::SetWhitePagesTrustedNetworks { copt, networks } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_domain_set_white_pages_trusted_networks(networks)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            DomainOpt::RemoveWhitePagesTrustedNetworks { copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_domain_remove_white_pages_trusted_networks()
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            DomainOpt::SetWhitePagesRateLimit { copt, rate_limit } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_domain_set_white_pages_rate_limit(*rate_limit)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            DomainOpt::RemoveWhitePagesRateLimit { copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_domain_remove_white_pages_rate_limit().await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
             DomainOpt::Show(copt) => {
                 let client = copt.to_client(OpType::Read).await;
                 match client.idm_domain_get().await {
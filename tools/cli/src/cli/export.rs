@@ -0,0 +1,138 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use kanidm_proto::constants::{
+    ATTR_CLASS, ATTR_MEMBER, ATTR_NAME, ATTR_UUID, ENTRYCLASS_GROUP, ENTRYCLASS_PERSON,
+};
+use kanidm_proto::internal::Filter;
+use kanidm_proto::v1::Entry;
+
+use crate::common::OpType;
+use crate::ldif::write_ldif_record;
+use crate::{handle_client_error, EntryFormat, ExportOpt};
+
+/// The attributes of people and groups that can be imported again. Other attributes are
+/// either generated by the server, or are credentials which can't be exported.
+const EXPORT_ATTRS: [&str; 13] = [
+    "account_expire",
+    "account_valid_from",
+    "class",
+    "description",
+    "displayname",
+    "gidnumber",
+    "legalname",
+    "loginshell",
+    "mail",
+    "member",
+    "name",
+    "ssh_publickey",
+    "uuid",
+];
+
+impl ExportOpt {
+    pub fn debug(&self) -> bool {
+        self.commonopts.debug
+    }
+
+    fn write_entry<W: Write>(&self, w: &mut W, entry: &Entry) -> Result<(), String> {
+        match self.format {
+            EntryFormat::Json => {
+                serde_json::to_writer(&mut *w, &entry.attrs)
+                    .map_err(|e| format!("Failed to write json: {:?}", e))?;
+                writeln!(w).map_err(|e| format!("Failed to write json: {:?}", e))
+            }
+            EntryFormat::Ldif => {
+                let name = entry
+                    .attrs
+                    .get(ATTR_NAME)
+                    .and_then(|values| values.first())
+                    .map(String::as_str)
+                    .unwrap_or_default();
+                let dn = match &self.base_dn {
+                    Some(base_dn) => format!("name={},{}", name, base_dn),
+                    None => format!("name={}", name),
+                };
+                write_ldif_record(w, &dn, entry)
+                    .map_err(|e| format!("Failed to write ldif: {:?}", e))
+            }
+        }
+    }
+
+    pub async fn exec(&self) {
+        let client = self.commonopts.to_client(OpType::Read).await;
+
+        let filter = Filter::Or(vec![
+            Filter::Eq(ATTR_CLASS.to_string(), ENTRYCLASS_PERSON.to_string()),
+            Filter::Eq(ATTR_CLASS.to_string(), ENTRYCLASS_GROUP.to_string()),
+        ]);
+
+        let entries = match client.search(filter).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                handle_client_error(e, self.commonopts.output_mode);
+                return;
+            }
+        };
+
+        let mut w: Box<dyn Write> = match &self.output {
+            Some(path) => match File::create(path) {
+                Ok(f) => Box::new(BufWriter::new(f)),
+                Err(e) => {
+                    error!("Unable to create {}: {:?}", path.display(), e);
+                    return;
+                }
+            },
+            None => Box::new(BufWriter::new(std::io::stdout().lock())),
+        };
+
+        let mut count = 0;
+
+        for entry in entries.into_iter().filter_map(exportable) {
+            if let Err(e) = self.write_entry(&mut w, &entry) {
+                error!("{}", e);
+                return;
+            }
+            count += 1;
+        }
+
+        if let Err(e) = w.flush() {
+            error!("Failed to write entries: {:?}", e);
+            return;
+        }
+
+        if self.output.is_some() {
+            println!("exported: {}", count);
+        }
+    }
+}
+
+/// Reduce an entry to the attributes that can be imported. Builtin entries are not exported,
+/// as they exist on every server.
+fn exportable(mut entry: Entry) -> Option<Entry> {
+    let builtin = entry
+        .attrs
+        .get(ATTR_UUID)
+        .and_then(|values| values.first())
+        .map(|uuid| uuid.starts_with("00000000-0000-0000-0000-"))
+        .unwrap_or(true);
+
+    if builtin {
+        return None;
+    }
+
+    entry
+        .attrs
+        .retain(|attr, _| EXPORT_ATTRS.contains(&attr.as_str()));
+
+    // Members are exported by name rather than spn, so that they can be imported to a server
+    // with a different domain.
+    if let Some(members) = entry.attrs.get_mut(ATTR_MEMBER) {
+        for member in members.iter_mut() {
+            if let Some((name, _)) = member.split_once('@') {
+                *member = name.to_string();
+            }
+        }
+    }
+
+    Some(entry)
+}
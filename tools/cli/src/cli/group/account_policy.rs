@@ -6,18 +6,56 @@ impl GroupAccountPolicyOpt {
         match self {
             GroupAccountPolicyOpt::Enable { copt, .. }
             | GroupAccountPolicyOpt::AuthSessionExpiry { copt, .. }
+            | GroupAccountPolicyOpt::AuthSessionIdleTimeout { copt, .. }
             | GroupAccountPolicyOpt::CredentialTypeMinimum { copt, .. }
             | GroupAccountPolicyOpt::PasswordMinimumLength { copt, .. }
+            | GroupAccountPolicyOpt::PasswordHistoryCount { copt, .. }
+            | GroupAccountPolicyOpt::PasswordMaximumAge { copt, .. }
             | GroupAccountPolicyOpt::WebauthnAttestationCaList { copt, .. }
             | GroupAccountPolicyOpt::LimitSearchMaxResults { copt, .. }
             | GroupAccountPolicyOpt::LimitSearchMaxFilterTest { copt, .. }
             | GroupAccountPolicyOpt::AllowPrimaryCredFallback { copt, .. }
+            | GroupAccountPolicyOpt::AllowUnixPassword { copt, .. }
+            | GroupAccountPolicyOpt::AllowApiTokens { copt, .. }
+            | GroupAccountPolicyOpt::AllowEmailLink { copt, .. }
+            | GroupAccountPolicyOpt::PasswordBreachCheck { copt, .. }
+            | GroupAccountPolicyOpt::LoginHostTag { copt, .. }
+            | GroupAccountPolicyOpt::SshKeyAllowedType { copt, .. }
+            | GroupAccountPolicyOpt::SshKeyRsaMinimumBits { copt, .. }
+            | GroupAccountPolicyOpt::PosixDefaultShell { copt, .. }
+            | GroupAccountPolicyOpt::PosixHomeTemplate { copt, .. }
+            | GroupAccountPolicyOpt::PosixGecosFormat { copt, .. }
+            | GroupAccountPolicyOpt::PosixHomeSkeleton { copt, .. }
+            | GroupAccountPolicyOpt::TrustedNetwork { copt, .. }
+            | GroupAccountPolicyOpt::CredentialTypeMinimumUntrustedNetwork { copt, .. }
+            | GroupAccountPolicyOpt::AuthLockoutThreshold { copt, .. }
+            | GroupAccountPolicyOpt::AuthLockoutWindow { copt, .. }
+            | GroupAccountPolicyOpt::AuthLockoutDuration { copt, .. }
+            | GroupAccountPolicyOpt::AccountLifetime { copt, .. }
+            | GroupAccountPolicyOpt::AccountArchiveDelay { copt, .. }
             | GroupAccountPolicyOpt::ResetWebauthnAttestationCaList { copt, .. }
             | GroupAccountPolicyOpt::ResetAuthSessionExpiry { copt, .. }
+            | GroupAccountPolicyOpt::ResetAuthSessionIdleTimeout { copt, .. }
             | GroupAccountPolicyOpt::ResetPasswordMinimumLength { copt, .. }
+            | GroupAccountPolicyOpt::ResetPasswordHistoryCount { copt, .. }
+            | GroupAccountPolicyOpt::ResetPasswordMaximumAge { copt, .. }
             | GroupAccountPolicyOpt::ResetPrivilegedSessionExpiry { copt, .. }
             | GroupAccountPolicyOpt::ResetLimitSearchMaxResults { copt, .. }
             | GroupAccountPolicyOpt::ResetLimitSearchMaxFilterTest { copt, .. }
+            | GroupAccountPolicyOpt::ResetLoginHostTag { copt, .. }
+            | GroupAccountPolicyOpt::ResetSshKeyAllowedType { copt, .. }
+            | GroupAccountPolicyOpt::ResetSshKeyRsaMinimumBits { copt, .. }
+            | GroupAccountPolicyOpt::ResetPosixDefaultShell { copt, .. }
+            | GroupAccountPolicyOpt::ResetPosixHomeTemplate { copt, .. }
+            | GroupAccountPolicyOpt::ResetPosixGecosFormat { copt, .. }
+            | GroupAccountPolicyOpt::ResetPosixHomeSkeleton { copt, .. }
+            | GroupAccountPolicyOpt::ResetTrustedNetwork { copt, .. }
+            | GroupAccountPolicyOpt::ResetCredentialTypeMinimumUntrustedNetwork { copt, .. }
+            | GroupAccountPolicyOpt::ResetAuthLockoutThreshold { copt, .. }
+            | GroupAccountPolicyOpt::ResetAuthLockoutWindow { copt, .. }
+            | GroupAccountPolicyOpt::ResetAuthLockoutDuration { copt, .. }
+            | GroupAccountPolicyOpt::ResetAccountLifetime { copt, .. }
+            | GroupAccountPolicyOpt::ResetAccountArchiveDelay { copt, .. }
             | GroupAccountPolicyOpt::PrivilegedSessionExpiry { copt, .. } => copt.debug,
         }
     }
@@ -56,6 +94,34 @@ impl GroupAccountPolicyOpt {
                 }
             }
 
+            GroupAccountPolicyOpt::AuthSessionIdleTimeout {
+                name,
+                timeout,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_authsession_idle_timeout_set(name, *timeout)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated authsession idle timeout.");
+                }
+            }
+
+            GroupAccountPolicyOpt::ResetAuthSessionIdleTimeout { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_authsession_idle_timeout_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset authsession idle timeout.");
+                }
+            }
+
             GroupAccountPolicyOpt::CredentialTypeMinimum { name, value, copt } => {
                 let client = copt.to_client(OpType::Write).await;
                 if let Err(e) = client
@@ -89,6 +155,50 @@ impl GroupAccountPolicyOpt {
                     println!("Successfully reset password minimum length.");
                 }
             }
+            GroupAccountPolicyOpt::PasswordHistoryCount { name, count, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_password_history_count_set(name, *count)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated password history count.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetPasswordHistoryCount { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_password_history_count_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset password history count.");
+                }
+            }
+            GroupAccountPolicyOpt::PasswordMaximumAge { name, age, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_password_maximum_age_set(name, *age)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated password maximum age.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetPasswordMaximumAge { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_password_maximum_age_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset password maximum age.");
+                }
+            }
             GroupAccountPolicyOpt::PrivilegedSessionExpiry { name, expiry, copt } => {
                 let client = copt.to_client(OpType::Write).await;
                 if let Err(e) = client
@@ -207,6 +317,386 @@ impl GroupAccountPolicyOpt {
                     println!("Updated primary credential fallback policy.");
                 }
             }
+            GroupAccountPolicyOpt::AllowUnixPassword { name, allow, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_allow_unix_password(name, *allow)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated unix password policy.");
+                }
+            }
+            GroupAccountPolicyOpt::AllowApiTokens { name, allow, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_allow_api_tokens(name, *allow)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated api token policy.");
+                }
+            }
+            GroupAccountPolicyOpt::AllowEmailLink { name, allow, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_allow_email_link(name, *allow)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated email link policy.");
+                }
+            }
+            GroupAccountPolicyOpt::PasswordBreachCheck { name, enable, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_password_breach_check(name, *enable)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated password breach check policy.");
+                }
+            }
+            GroupAccountPolicyOpt::LoginHostTag { name, tags, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_login_host_tag_set(name, tags)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated login host tags.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetLoginHostTag { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client.group_account_policy_login_host_tag_reset(name).await {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset login host tags.");
+                }
+            }
+            GroupAccountPolicyOpt::SshKeyAllowedType {
+                name,
+                key_types,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_ssh_key_allowed_type_set(name, key_types)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated ssh key allowed types.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetSshKeyAllowedType { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_ssh_key_allowed_type_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset ssh key allowed types.");
+                }
+            }
+            GroupAccountPolicyOpt::SshKeyRsaMinimumBits { name, bits, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_ssh_key_rsa_minimum_bits_set(name, *bits)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated ssh key rsa minimum bits.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetSshKeyRsaMinimumBits { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_ssh_key_rsa_minimum_bits_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset ssh key rsa minimum bits.");
+                }
+            }
+            GroupAccountPolicyOpt::PosixDefaultShell { name, shell, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_posix_default_shell_set(name, shell)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated posix default shell.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetPosixDefaultShell { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_posix_default_shell_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset posix default shell.");
+                }
+            }
+            GroupAccountPolicyOpt::PosixHomeTemplate {
+                name,
+                template,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_posix_home_template_set(name, template)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated posix home template.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetPosixHomeTemplate { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_posix_home_template_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset posix home template.");
+                }
+            }
+            GroupAccountPolicyOpt::PosixGecosFormat { name, format, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_posix_gecos_format_set(name, format)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated posix gecos format.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetPosixGecosFormat { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_posix_gecos_format_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset posix gecos format.");
+                }
+            }
+            GroupAccountPolicyOpt::PosixHomeSkeleton {
+                name,
+                skeleton,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_posix_home_skeleton_set(name, skeleton)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated posix home skeleton.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetPosixHomeSkeleton { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_posix_home_skeleton_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset posix home skeleton.");
+                }
+            }
+            GroupAccountPolicyOpt::TrustedNetwork {
+                name,
+                networks,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_trusted_network_set(name, networks)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated trusted networks.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetTrustedNetwork { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_trusted_network_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset trusted networks.");
+                }
+            }
+            GroupAccountPolicyOpt::CredentialTypeMinimumUntrustedNetwork { name, value, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_credential_type_minimum_untrusted_network_set(
+                        name,
+                        value.as_str(),
+                    )
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated untrusted network credential type minimum.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetCredentialTypeMinimumUntrustedNetwork { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_credential_type_minimum_untrusted_network_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset untrusted network credential type minimum.");
+                }
+            }
+            GroupAccountPolicyOpt::AuthLockoutThreshold {
+                name,
+                threshold,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_auth_lockout_threshold_set(name, *threshold)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated lockout threshold.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetAuthLockoutThreshold { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_auth_lockout_threshold_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset lockout threshold.");
+                }
+            }
+            GroupAccountPolicyOpt::AuthLockoutWindow { name, window, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_auth_lockout_window_set(name, *window)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated lockout window.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetAuthLockoutWindow { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_auth_lockout_window_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset lockout window.");
+                }
+            }
+            GroupAccountPolicyOpt::AuthLockoutDuration {
+                name,
+                duration,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_auth_lockout_duration_set(name, *duration)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated lockout duration.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetAuthLockoutDuration { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_auth_lockout_duration_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset lockout duration.");
+                }
+            }
+            GroupAccountPolicyOpt::AccountLifetime {
+                name,
+                lifetime,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_account_lifetime_set(name, *lifetime)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated account lifetime.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetAccountLifetime { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_account_lifetime_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset account lifetime.");
+                }
+            }
+            GroupAccountPolicyOpt::AccountArchiveDelay { name, delay, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_account_archive_delay_set(name, *delay)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Updated account archive delay.");
+                }
+            }
+            GroupAccountPolicyOpt::ResetAccountArchiveDelay { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .group_account_policy_account_archive_delay_reset(name)
+                    .await
+                {
+                    handle_group_account_policy_error(e, copt.output_mode);
+                } else {
+                    println!("Successfully reset account archive delay.");
+                }
+            }
         }
     }
 }
@@ -0,0 +1,125 @@
+use crate::common::{try_duration_from_string, OpType};
+use crate::{handle_client_error, GroupMembershipRequestOpt, OutputMode};
+use kanidm_proto::v1::GroupMembershipRequest;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+fn display_request(request: &GroupMembershipRequest) {
+    println!("id: {}", request.uuid);
+    println!("group: {}", request.group);
+    println!("requester: {}", request.requester);
+    if let Some(justification) = &request.justification {
+        println!("justification: {}", justification);
+    }
+    println!("status: {}", request.status);
+    if let Some(decided_by) = &request.decided_by {
+        println!("decided by: {}", decided_by);
+    }
+    if let Some(Ok(valid_until)) = request.valid_until.map(|v| v.format(&Rfc3339)) {
+        println!("valid until: {}", valid_until);
+    }
+    if let Ok(requested_at) = request.requested_at.format(&Rfc3339) {
+        println!("requested at: {}", requested_at);
+    }
+    println!("---");
+}
+
+impl GroupMembershipRequestOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            GroupMembershipRequestOpt::Enable { copt, .. }
+            | GroupMembershipRequestOpt::Disable { copt, .. }
+            | GroupMembershipRequestOpt::Create { copt, .. }
+            | GroupMembershipRequestOpt::List(copt)
+            | GroupMembershipRequestOpt::Approve { copt, .. }
+            | GroupMembershipRequestOpt::Deny { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            GroupMembershipRequestOpt::Enable { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.group_membership_request_enable(name).await {
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                    Ok(_) => println!("Accounts may now request to join group {}", name),
+                }
+            }
+            GroupMembershipRequestOpt::Disable { name, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.group_membership_request_disable(name).await {
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                    Ok(_) => println!("Accounts may no longer request to join group {}", name),
+                }
+            }
+            GroupMembershipRequestOpt::Create {
+                name,
+                justification,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_group_membership_request(name, justification.clone())
+                    .await
+                {
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                    Ok(request_id) => println!(
+                        "Requested to join group {}, the request id is {}",
+                        name, request_id
+                    ),
+                }
+            }
+            GroupMembershipRequestOpt::List(copt) => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_group_membership_request_list().await {
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                    Ok(requests) => match copt.output_mode {
+                        OutputMode::Json => println!(
+                            "{}",
+                            serde_json::to_string(&requests).expect("Failed to serialise json")
+                        ),
+                        OutputMode::Text => {
+                            if !requests.awaiting_approval.is_empty() {
+                                println!("Awaiting your approval:");
+                                requests.awaiting_approval.iter().for_each(display_request);
+                            }
+                            if requests.requested.is_empty() {
+                                println!("You have not requested to join any groups.");
+                            } else {
+                                println!("Your requests:");
+                                requests.requested.iter().for_each(display_request);
+                            }
+                        }
+                    },
+                }
+            }
+            GroupMembershipRequestOpt::Approve {
+                id,
+                expire_in,
+                copt,
+            } => {
+                let valid_until = match expire_in.as_deref().map(try_duration_from_string) {
+                    Some(Ok(expire_in)) => Some(OffsetDateTime::now_utc() + expire_in),
+                    Some(Err(())) => return,
+                    None => None,
+                };
+
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_group_membership_request_approve(*id, valid_until)
+                    .await
+                {
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                    Ok(_) => println!("Approved request {}", id),
+                }
+            }
+            GroupMembershipRequestOpt::Deny { id, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_group_membership_request_deny(*id).await {
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                    Ok(_) => println!("Denied request {}", id),
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use crate::common::OpType;
+use crate::common::{try_duration_from_string, OpType};
 use crate::{handle_client_error, GroupOpt, GroupPosix, OutputMode};
 use kanidm_proto::constants::ATTR_GIDNUMBER;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
 mod account_policy;
+mod membership_request;
 
 impl GroupOpt {
     pub fn debug(&self) -> bool {
@@ -10,6 +13,8 @@ impl GroupOpt {
             GroupOpt::List(copt) | GroupOpt::Search { copt, .. } => copt.debug,
             GroupOpt::Get(gcopt) => gcopt.copt.debug,
             GroupOpt::SetEntryManagedBy { copt, .. } | GroupOpt::Create { copt, .. } => copt.debug,
+            GroupOpt::SetParent { copt, .. } => copt.debug,
+            GroupOpt::ResetParent(gcopt) => gcopt.copt.debug,
             GroupOpt::Delete(gcopt) => gcopt.copt.debug,
             GroupOpt::ListMembers(gcopt) => gcopt.copt.debug,
             GroupOpt::AddMembers(gcopt) => gcopt.copt.debug,
@@ -23,6 +28,7 @@ impl GroupOpt {
                 GroupPosix::ResetGidnumber { copt, .. } => copt.debug,
             },
             GroupOpt::AccountPolicy { commands } => commands.debug(),
+            GroupOpt::MembershipRequest { commands } => commands.debug(),
         }
     }
 
@@ -119,19 +125,47 @@ impl GroupOpt {
                 }
             }
             GroupOpt::AddMembers(gcopt) => {
+                let valid_until = match gcopt.expire_in.as_deref().map(try_duration_from_string) {
+                    Some(Ok(expire_in)) => Some(OffsetDateTime::now_utc() + expire_in),
+                    Some(Err(())) => return,
+                    None => None,
+                };
+
                 let client = gcopt.copt.to_client(OpType::Write).await;
                 let new_members: Vec<&str> = gcopt.members.iter().map(String::as_str).collect();
 
-                match client
-                    .idm_group_add_members(gcopt.name.as_str(), &new_members)
-                    .await
-                {
+                let result = match valid_until {
+                    Some(valid_until) => {
+                        client
+                            .idm_group_add_temporary_members(
+                                gcopt.name.as_str(),
+                                &new_members,
+                                valid_until,
+                            )
+                            .await
+                    }
+                    None => {
+                        client
+                            .idm_group_add_members(gcopt.name.as_str(), &new_members)
+                            .await
+                    }
+                };
+
+                match result {
                     Err(e) => handle_client_error(e, gcopt.copt.output_mode),
-                    Ok(_) => println!(
-                        "Successfully added {:?} to group \"{}\"",
-                        &new_members,
-                        gcopt.name.as_str()
-                    ),
+                    Ok(_) => match valid_until.map(|valid_until| valid_until.format(&Rfc3339)) {
+                        Some(Ok(valid_until)) => println!(
+                            "Successfully added {:?} to group \"{}\" until {}",
+                            &new_members,
+                            gcopt.name.as_str(),
+                            valid_until
+                        ),
+                        _ => println!(
+                            "Successfully added {:?} to group \"{}\"",
+                            &new_members,
+                            gcopt.name.as_str()
+                        ),
+                    },
                 }
             }
 
@@ -210,6 +244,25 @@ impl GroupOpt {
                     ),
                 }
             }
+            GroupOpt::SetParent { name, parent, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+
+                match client.idm_group_set_parent(name, parent).await {
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                    Ok(_) => println!(
+                        "Successfully set parent to '{}' for group '{}'",
+                        parent, name
+                    ),
+                }
+            }
+            GroupOpt::ResetParent(gcopt) => {
+                let client = gcopt.copt.to_client(OpType::Write).await;
+
+                match client.idm_group_purge_parent(gcopt.name.as_str()).await {
+                    Err(e) => handle_client_error(e, gcopt.copt.output_mode),
+                    Ok(_) => println!("Successfully removed the parent of group '{}'", gcopt.name),
+                }
+            }
             GroupOpt::Posix { commands } => match commands {
                 GroupPosix::Show(gcopt) => {
                     let client = gcopt.copt.to_client(OpType::Read).await;
@@ -242,6 +295,7 @@ impl GroupOpt {
                 }
             },
             GroupOpt::AccountPolicy { commands } => commands.exec().await,
+            GroupOpt::MembershipRequest { commands } => commands.exec().await,
         } // end match
     }
 }
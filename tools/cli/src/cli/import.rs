@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+
+use kanidm_client::KanidmClient;
+use kanidm_proto::internal::BulkImportPhase;
+use kanidm_proto::v1::Entry;
+use serde::Serialize;
+
+use crate::common::OpType;
+use crate::ldif::{LdifMapping, LdifReader};
+use crate::{handle_client_error, EntryFormat, ImportOpt, OutputMode};
+
+type EntryIter = Box<dyn Iterator<Item = Result<Entry, String>>>;
+
+#[derive(Debug, Serialize)]
+struct ImportFailure {
+    /// The position of the entry in the file, starting from 1.
+    entry: usize,
+    name: Option<String>,
+    phase: &'static str,
+    error: String,
+}
+
+impl ImportOpt {
+    pub fn debug(&self) -> bool {
+        self.commonopts.debug
+    }
+
+    fn ldif_mapping(&self) -> Result<LdifMapping, String> {
+        match &self.mapping {
+            Some(path) => {
+                let f = File::open(path)
+                    .map_err(|e| format!("Unable to open {}: {:?}", path.display(), e))?;
+                serde_json::from_reader(BufReader::new(f))
+                    .map_err(|e| format!("Invalid mapping {}: {:?}", path.display(), e))
+            }
+            None => Ok(LdifMapping::from_profile(self.profile)),
+        }
+    }
+
+    fn open(&self, mapping: &LdifMapping) -> Result<EntryIter, String> {
+        let f = File::open(&self.file)
+            .map_err(|e| format!("Unable to open {}: {:?}", self.file.display(), e))?;
+        let r = BufReader::new(f);
+
+        Ok(match self.format {
+            EntryFormat::Json => Box::new(
+                serde_json::Deserializer::from_reader(r)
+                    .into_iter::<BTreeMap<String, Vec<String>>>()
+                    .map(|attrs| {
+                        attrs
+                            .map(|attrs| Entry { attrs })
+                            .map_err(|e| format!("Invalid json: {:?}", e))
+                    }),
+            ),
+            EntryFormat::Ldif => Box::new(LdifReader::new(r, mapping.clone())),
+        })
+    }
+
+    /// Send every entry of the file to the server for one phase of the import. The file is
+    /// read again for each phase, so that large imports are never held in memory.
+    async fn import_phase(
+        &self,
+        client: &KanidmClient,
+        mapping: &LdifMapping,
+        phase: BulkImportPhase,
+        failures: &mut BTreeMap<usize, ImportFailure>,
+    ) -> Result<usize, String> {
+        let batch_size = self.batch_size.max(1);
+        let phase_name = match phase {
+            BulkImportPhase::Entries => "entries",
+            BulkImportPhase::References => "references",
+        };
+
+        let mut entries = self.open(mapping)?;
+        let mut count = 0;
+
+        loop {
+            let mut batch = Vec::with_capacity(batch_size);
+            let mut positions = Vec::with_capacity(batch_size);
+
+            while batch.len() < batch_size {
+                let Some(entry) = entries.next() else {
+                    break;
+                };
+                count += 1;
+                let entry = entry.map_err(|e| format!("Entry {}: {}", count, e))?;
+
+                // An entry that failed to be created can't have its references added.
+                if !failures.contains_key(&count) {
+                    batch.push(entry);
+                    positions.push(count);
+                }
+            }
+
+            if batch.is_empty() {
+                break;
+            }
+
+            let names: Vec<_> = batch
+                .iter()
+                .map(|entry| {
+                    entry
+                        .attrs
+                        .get("name")
+                        .and_then(|values| values.first())
+                        .cloned()
+                })
+                .collect();
+
+            let response = client.bulk_import(phase, batch).await.map_err(|e| {
+                handle_client_error(e, self.commonopts.output_mode);
+                format!("Failed to import {}", phase_name)
+            })?;
+
+            for failure in response.failures {
+                let (Some(entry), Some(name)) =
+                    (positions.get(failure.index), names.get(failure.index))
+                else {
+                    continue;
+                };
+                failures.insert(
+                    *entry,
+                    ImportFailure {
+                        entry: *entry,
+                        name: name.clone(),
+                        phase: phase_name,
+                        error: failure.error.to_string(),
+                    },
+                );
+            }
+
+            if matches!(self.commonopts.output_mode, OutputMode::Text) {
+                eprintln!("{}: processed {} entries", phase_name, count);
+            }
+        }
+
+        Ok(count)
+    }
+
+    pub async fn exec(&self) {
+        let mapping = match self.ldif_mapping() {
+            Ok(mapping) => mapping,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+
+        let client = self.commonopts.to_client(OpType::Write).await;
+
+        let mut failures = BTreeMap::new();
+        let mut count = 0;
+
+        for phase in [BulkImportPhase::Entries, BulkImportPhase::References] {
+            match self
+                .import_phase(&client, &mapping, phase, &mut failures)
+                .await
+            {
+                Ok(c) => count = c,
+                Err(e) => {
+                    error!("{}", e);
+                    return;
+                }
+            }
+        }
+
+        let imported = count - failures.len();
+        let failures: Vec<_> = failures.into_values().collect();
+
+        match self.commonopts.output_mode {
+            OutputMode::Json => {
+                #[derive(Serialize)]
+                struct ImportSummary {
+                    imported: usize,
+                    failures: Vec<ImportFailure>,
+                }
+
+                let summary = ImportSummary { imported, failures };
+                #[allow(clippy::expect_used)]
+                let json = serde_json::to_string(&summary).expect("Failed to serialise json");
+                println!("{}", json);
+            }
+            OutputMode::Text => {
+                println!("imported: {}", imported);
+                println!("failed: {}", failures.len());
+                for failure in failures {
+                    println!(
+                        "entry {} ({}), {}: {}",
+                        failure.entry,
+                        failure.name.as_deref().unwrap_or("no name"),
+                        failure.phase,
+                        failure.error
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,513 @@
+//! Reading and writing of ldif content records, and the mapping of ldap attributes and object
+//! classes from other directory servers to kanidm.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, Lines, Write};
+
+use kanidm_proto::v1::Entry;
+use serde::Deserialize;
+
+use crate::LdifProfile;
+
+/// Maps an ldap attribute to a kanidm attribute.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct AttributeMapping {
+    pub ldap: String,
+    pub kanidm: String,
+    /// Only map the attribute of records that are imported with this kanidm class.
+    #[serde(default)]
+    pub class: Option<String>,
+}
+
+/// How the records of an ldif file are imported as kanidm entries.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct LdifMapping {
+    /// Import the attributes that aren't mapped with their lowercased names.
+    #[serde(default)]
+    pub passthrough: bool,
+    /// The kanidm classes that each ldap object class is imported as. Records that don't have
+    /// a mapped object class are skipped. If this is empty, object classes are imported as
+    /// classes of the same name.
+    #[serde(default)]
+    pub classes: BTreeMap<String, Vec<String>>,
+    /// If more than one mapping has the same kanidm attribute, the first mapping that
+    /// applies to a record is used.
+    #[serde(default)]
+    pub attributes: Vec<AttributeMapping>,
+}
+
+impl LdifMapping {
+    pub fn from_profile(profile: LdifProfile) -> Self {
+        match profile {
+            LdifProfile::Kanidm => LdifMapping {
+                passthrough: true,
+                ..Default::default()
+            },
+            LdifProfile::Openldap => Self::openldap(),
+        }
+    }
+
+    /// The inetOrgPerson, groupOfNames and rfc2307 (posix) schema, as used by OpenLDAP and
+    /// 389 Directory Server.
+    fn openldap() -> Self {
+        let classes = [
+            ("inetorgperson", &["person", "account"][..]),
+            ("organizationalperson", &["person", "account"]),
+            ("person", &["person", "account"]),
+            ("posixaccount", &["posixaccount"]),
+            ("groupofnames", &["group"]),
+            ("groupofuniquenames", &["group"]),
+            ("posixgroup", &["group", "posixgroup"]),
+        ]
+        .into_iter()
+        .map(|(ldap, kanidm)| {
+            (
+                ldap.to_string(),
+                kanidm.iter().map(|c| c.to_string()).collect(),
+            )
+        })
+        .collect();
+
+        let attributes = [
+            ("uid", "name", Some("person")),
+            ("cn", "name", Some("group")),
+            ("displayname", "displayname", Some("person")),
+            ("cn", "displayname", Some("person")),
+            ("mail", "mail", None),
+            ("description", "description", None),
+            ("uidnumber", "gidnumber", Some("posixaccount")),
+            ("gidnumber", "gidnumber", Some("posixgroup")),
+            ("loginshell", "loginshell", Some("posixaccount")),
+            ("sshpublickey", "ssh_publickey", Some("person")),
+            ("userpassword", "password_import", Some("person")),
+            ("member", "member", Some("group")),
+            ("uniquemember", "member", Some("group")),
+            ("memberuid", "member", Some("group")),
+        ]
+        .into_iter()
+        .map(|(ldap, kanidm, class)| AttributeMapping {
+            ldap: ldap.to_string(),
+            kanidm: kanidm.to_string(),
+            class: class.map(str::to_string),
+        })
+        .collect();
+
+        LdifMapping {
+            passthrough: false,
+            classes,
+            attributes,
+        }
+    }
+
+    /// Map the attributes of an ldif record to an entry. Returns `None` if the record has no
+    /// object class that is imported.
+    fn apply(&self, dn: &str, mut ldap_attrs: BTreeMap<String, Vec<String>>) -> Option<Entry> {
+        // memberOf is maintained by kanidm from the members of groups.
+        ldap_attrs.remove("memberof");
+
+        let object_classes = ldap_attrs.remove("objectclass").unwrap_or_default();
+
+        let classes: BTreeSet<String> = if self.classes.is_empty() {
+            object_classes.into_iter().collect()
+        } else {
+            object_classes
+                .iter()
+                .filter_map(|oc| self.classes.get(oc))
+                .flatten()
+                .cloned()
+                .collect()
+        };
+
+        if classes.is_empty() && !self.classes.is_empty() {
+            return None;
+        }
+
+        let mut entry = Entry::default();
+
+        for mapping in self.attributes.iter() {
+            if entry.attrs.contains_key(&mapping.kanidm) {
+                continue;
+            }
+            if let Some(class) = &mapping.class {
+                if !classes.contains(class) {
+                    continue;
+                }
+            }
+            if let Some(values) = ldap_attrs.get(&mapping.ldap) {
+                entry.attrs.insert(mapping.kanidm.clone(), values.clone());
+            }
+        }
+
+        if self.passthrough {
+            for (attr, values) in ldap_attrs {
+                if !self.attributes.iter().any(|mapping| mapping.ldap == attr) {
+                    entry.attrs.entry(attr).or_insert(values);
+                }
+            }
+        }
+
+        if !classes.is_empty() {
+            entry
+                .attrs
+                .insert("class".to_string(), classes.into_iter().collect());
+        }
+
+        if !entry.attrs.contains_key("name") {
+            if let Some(name) = dn
+                .split(',')
+                .next()
+                .and_then(|rdn| rdn.split_once('='))
+                .map(|(_, value)| value.trim().to_string())
+            {
+                entry.attrs.insert("name".to_string(), vec![name]);
+            }
+        }
+
+        Some(entry)
+    }
+}
+
+/// Reads the content records of an ldif file one at a time, and maps them to entries.
+/// Attribute names and object classes are lowercased before they are mapped.
+pub(crate) struct LdifReader<R> {
+    lines: Lines<R>,
+    mapping: LdifMapping,
+}
+
+impl<R: BufRead> LdifReader<R> {
+    pub fn new(r: R, mapping: LdifMapping) -> Self {
+        LdifReader {
+            lines: r.lines(),
+            mapping,
+        }
+    }
+
+    /// Read the next record as a list of unfolded lines, skipping comments.
+    fn next_record(&mut self) -> Result<Vec<String>, String> {
+        let mut record: Vec<String> = Vec::new();
+
+        for line in self.lines.by_ref() {
+            let line = line.map_err(|e| format!("Unable to read ldif: {:?}", e))?;
+
+            if line.is_empty() {
+                if record.is_empty() {
+                    continue;
+                }
+                break;
+            } else if line.starts_with('#') {
+                continue;
+            } else if let Some(folded) = line.strip_prefix(' ') {
+                match record.last_mut() {
+                    Some(last) => last.push_str(folded),
+                    None => return Err("Invalid ldif: continuation of no line".to_string()),
+                }
+            } else {
+                record.push(line);
+            }
+        }
+
+        Ok(record)
+    }
+}
+
+impl<R: BufRead> Iterator for LdifReader<R> {
+    type Item = Result<Entry, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let record = match self.next_record() {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            };
+
+            if record.is_empty() {
+                return None;
+            }
+
+            match parse_ldif_record(&record) {
+                Ok(Some((dn, attrs))) => match self.mapping.apply(&dn, attrs) {
+                    Some(entry) => return Some(Ok(entry)),
+                    // Such as organisational units, which aren't imported.
+                    None => continue,
+                },
+                // A record with only a version has no entry.
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+type LdifRecord = (String, BTreeMap<String, Vec<String>>);
+
+fn parse_ldif_record(record: &[String]) -> Result<Option<LdifRecord>, String> {
+    let mut dn = None;
+    let mut attrs: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for line in record {
+        let (attr, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid ldif line: {}", line))?;
+
+        let value = if let Some(encoded) = value.strip_prefix(':') {
+            let decoded = decode_base64(encoded.trim())
+                .ok_or_else(|| format!("Invalid base64 value of {}", attr))?;
+            String::from_utf8(decoded).map_err(|_| format!("Invalid utf8 value of {}", attr))?
+        } else if value.starts_with('<') {
+            return Err(format!(
+                "The value of {} refers to a url, which is not supported",
+                attr
+            ));
+        } else {
+            value.trim_start().to_string()
+        };
+
+        let attr = attr.to_lowercase();
+
+        match attr.as_str() {
+            "dn" => dn = Some(value),
+            // The version of the file, which precedes the dn of the first record.
+            "version" if dn.is_none() => {}
+            "changetype" => {
+                return Err("Only ldif content records can be imported".to_string());
+            }
+            "objectclass" => attrs.entry(attr).or_default().push(value.to_lowercase()),
+            _ => attrs.entry(attr).or_default().push(value),
+        }
+    }
+
+    match dn {
+        Some(dn) => Ok(Some((dn, attrs))),
+        None if attrs.is_empty() => Ok(None),
+        None => Err("Invalid ldif: record has no dn".to_string()),
+    }
+}
+
+/// Write an entry as an ldif content record. Values that can't be written as plain text
+/// are base64 encoded.
+pub(crate) fn write_ldif_record<W: Write>(
+    w: &mut W,
+    dn: &str,
+    entry: &Entry,
+) -> std::io::Result<()> {
+    write_ldif_value(w, "dn", dn)?;
+    for (attr, values) in entry.attrs.iter() {
+        for value in values {
+            write_ldif_value(w, attr, value)?;
+        }
+    }
+    writeln!(w)
+}
+
+fn write_ldif_value<W: Write>(w: &mut W, attr: &str, value: &str) -> std::io::Result<()> {
+    let safe = value
+        .bytes()
+        .all(|c| c.is_ascii() && c != b'\n' && c != b'\r' && c != b'\0')
+        && !value.starts_with([' ', ':', '<'])
+        && !value.ends_with(' ');
+
+    if safe {
+        writeln!(w, "{}: {}", attr, value)
+    } else {
+        writeln!(w, "{}:: {}", attr, encode_base64(value.as_bytes()))
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+
+    for c in encoded.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+
+        acc = (acc << 6) | u32::from(v);
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_ldif_record, LdifMapping, LdifReader};
+    use crate::LdifProfile;
+
+    #[test]
+    fn test_ldif_reader() {
+        let ldif = "version: 1
+
+# A comment
+dn: uid=alice,ou=people,dc=example,dc=com
+objectClass: person
+objectClass: account
+displayName: Alice
+ Smith
+
+dn: cn=staff,ou=groups,dc=example,dc=com
+objectclass: group
+name: staff
+member: uid=alice,ou=people,dc=example,dc=com
+memberOf: cn=everyone,dc=example,dc=com
+description:: w4lxdWlwZQ==
+";
+
+        let mapping = LdifMapping::from_profile(LdifProfile::Kanidm);
+        let entries: Vec<_> = LdifReader::new(ldif.as_bytes(), mapping)
+            .collect::<Result<_, _>>()
+            .expect("Failed to parse ldif");
+
+        assert_eq!(entries.len(), 2);
+
+        let alice = &entries[0];
+        assert_eq!(alice.attrs["name"], vec!["alice".to_string()]);
+        assert_eq!(
+            alice.attrs["class"],
+            vec!["account".to_string(), "person".to_string()]
+        );
+        assert_eq!(alice.attrs["displayname"], vec!["AliceSmith".to_string()]);
+
+        let staff = &entries[1];
+        assert_eq!(staff.attrs["name"], vec!["staff".to_string()]);
+        assert_eq!(
+            staff.attrs["member"],
+            vec!["uid=alice,ou=people,dc=example,dc=com".to_string()]
+        );
+        assert_eq!(staff.attrs["description"], vec!["Équipe".to_string()]);
+        assert!(!staff.attrs.contains_key("memberof"));
+    }
+
+    #[test]
+    fn test_ldif_reader_changes() {
+        let ldif = "dn: uid=alice,dc=example,dc=com
+changetype: delete
+";
+
+        let mapping = LdifMapping::from_profile(LdifProfile::Kanidm);
+        let mut reader = LdifReader::new(ldif.as_bytes(), mapping);
+        assert!(matches!(reader.next(), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_ldif_openldap_profile() {
+        let ldif = "dn: ou=people,dc=example,dc=com
+objectClass: organizationalUnit
+ou: people
+
+dn: uid=alice,ou=people,dc=example,dc=com
+objectClass: inetOrgPerson
+objectClass: posixAccount
+objectClass: ldapPublicKey
+uid: alice
+cn: Alice Smith
+sn: Smith
+uidNumber: 2001
+gidNumber: 2001
+homeDirectory: /home/alice
+loginShell: /bin/zsh
+sshPublicKey: ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAeGW1P6Pc2rPq0XqbRaDKBcXZUPRklo0L1EyR30CwoP
+
+dn: cn=staff,ou=groups,dc=example,dc=com
+objectClass: posixGroup
+cn: staff
+gidNumber: 3001
+memberUid: alice
+";
+
+        let mapping = LdifMapping::from_profile(LdifProfile::Openldap);
+        let entries: Vec<_> = LdifReader::new(ldif.as_bytes(), mapping)
+            .collect::<Result<_, _>>()
+            .expect("Failed to parse ldif");
+
+        // The organisational unit is skipped.
+        assert_eq!(entries.len(), 2);
+
+        let alice = &entries[0];
+        assert_eq!(
+            alice.attrs["class"],
+            vec![
+                "account".to_string(),
+                "person".to_string(),
+                "posixaccount".to_string()
+            ]
+        );
+        assert_eq!(alice.attrs["name"], vec!["alice".to_string()]);
+        assert_eq!(alice.attrs["displayname"], vec!["Alice Smith".to_string()]);
+        assert_eq!(alice.attrs["gidnumber"], vec!["2001".to_string()]);
+        assert_eq!(alice.attrs["loginshell"], vec!["/bin/zsh".to_string()]);
+        assert!(alice.attrs.contains_key("ssh_publickey"));
+        assert!(!alice.attrs.contains_key("sn"));
+        assert!(!alice.attrs.contains_key("homedirectory"));
+
+        let staff = &entries[1];
+        assert_eq!(
+            staff.attrs["class"],
+            vec!["group".to_string(), "posixgroup".to_string()]
+        );
+        assert_eq!(staff.attrs["name"], vec!["staff".to_string()]);
+        assert_eq!(staff.attrs["gidnumber"], vec!["3001".to_string()]);
+        assert_eq!(staff.attrs["member"], vec!["alice".to_string()]);
+        assert!(!staff.attrs.contains_key("displayname"));
+    }
+
+    #[test]
+    fn test_ldif_write_read() {
+        let mut entry = kanidm_proto::v1::Entry::default();
+        entry
+            .attrs
+            .insert("name".to_string(), vec!["equipe".to_string()]);
+        entry
+            .attrs
+            .insert("description".to_string(), vec!["Équipe".to_string()]);
+
+        let mut ldif = Vec::new();
+        write_ldif_record(&mut ldif, "name=equipe", &entry).expect("Failed to write ldif");
+
+        let mapping = LdifMapping::from_profile(LdifProfile::Kanidm);
+        let entries: Vec<_> = LdifReader::new(ldif.as_slice(), mapping)
+            .collect::<Result<_, _>>()
+            .expect("Failed to parse ldif");
+
+        assert_eq!(entries, vec![entry]);
+    }
+}
@@ -24,22 +24,29 @@ use uuid::Uuid;
 
 include!("../opt/kanidm.rs");
 
+mod apply;
 mod common;
+mod debug;
 mod domain;
+mod export;
 mod graph;
 mod group;
+mod import;
+mod ldif;
 mod oauth2;
 mod person;
 mod raw;
 mod recycle;
 mod serviceaccount;
 mod session;
+mod shell;
 mod synch;
 mod system_config;
 mod webauthn;
 
 /// Throws an error and exits the program when we get an error
 pub(crate) fn handle_client_error(response: ClientError, _output_mode: OutputMode) {
+    let hint = response.hint();
     match response {
         ClientError::Http(status, error, opid) => {
             let error_msg = match &error {
@@ -47,6 +54,9 @@ pub(crate) fn handle_client_error(response: ClientError, _output_mode: OutputMod
                 None => "".to_string(),
             };
             error!("OperationId: {:?}", opid);
+            if let Some(hint) = hint {
+                error!("Hint: {}", hint);
+            }
             if status == StatusCode::INTERNAL_SERVER_ERROR {
                 error!("Internal Server Error in response: {}", error_msg);
                 std::process::exit(1);
@@ -89,6 +99,13 @@ impl SelfOpt {
         match self {
             SelfOpt::Whoami(copt) => copt.debug,
             SelfOpt::IdentifyUser(copt) => copt.debug,
+            SelfOpt::SshCertificate { copt, .. } => copt.debug,
+            SelfOpt::ShowSshCaPublicKeys { copt } => copt.debug,
+            SelfOpt::ApplicationPassword { commands } => match commands {
+                SelfApplicationPasswordOpt::List(copt) => copt.debug,
+                SelfApplicationPasswordOpt::Generate { copt, .. } => copt.debug,
+                SelfApplicationPasswordOpt::Revoke { copt, .. } => copt.debug,
+            },
         }
     }
 
@@ -141,6 +158,85 @@ impl SelfOpt {
 
                 run_identity_verification_no_tui(IdentifyUserState::Start, client, spn, None).await;
             } // end PersonOpt::Validity
+            SelfOpt::SshCertificate {
+                public_key_path,
+                copt,
+            } => {
+                let public_key = match tokio::fs::read_to_string(public_key_path).await {
+                    Ok(public_key) => public_key,
+                    Err(io_err) => {
+                        error!(?io_err, ?public_key_path, "Unable to read ssh public key");
+                        return;
+                    }
+                };
+
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_ssh_certificate_request(public_key.trim()).await {
+                    Ok(certificate) => println!("{}", certificate),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            SelfOpt::ShowSshCaPublicKeys { copt } => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_ssh_ca_public_keys_get().await {
+                    Ok(public_keys) => public_keys.iter().for_each(|k| println!("{}", k)),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            SelfOpt::ApplicationPassword { commands } => match commands {
+                SelfApplicationPasswordOpt::List(copt) => {
+                    let client = copt.to_client(OpType::Read).await;
+                    match client.idm_self_application_password_list().await {
+                        Ok(app_pwds) => match copt.output_mode {
+                            OutputMode::Json => println!(
+                                "{}",
+                                serde_json::to_string(&app_pwds).expect("Failed to serialise json")
+                            ),
+                            OutputMode::Text => {
+                                if app_pwds.is_empty() {
+                                    println!("No application passwords");
+                                }
+                                for ap in app_pwds {
+                                    println!("---");
+                                    println!("id: {}", ap.uuid);
+                                    println!("application: {}", ap.application);
+                                    println!("label: {}", ap.label);
+                                }
+                            }
+                        },
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
+                SelfApplicationPasswordOpt::Generate {
+                    application,
+                    label,
+                    copt,
+                } => {
+                    let client = copt.to_client(OpType::Write).await;
+                    match client
+                        .idm_self_application_password_generate(application, label)
+                        .await
+                    {
+                        Ok(password) => {
+                            println!(
+                                "The application password is shown below, it can't be shown again."
+                            );
+                            println!("{}", password);
+                        }
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
+                SelfApplicationPasswordOpt::Revoke { password_id, copt } => {
+                    let client = copt.to_client(OpType::Write).await;
+                    match client
+                        .idm_self_application_password_revoke(*password_id)
+                        .await
+                    {
+                        Ok(()) => println!("Success"),
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
+            },
         }
     }
 }
@@ -151,9 +247,19 @@ impl SystemOpt {
             SystemOpt::Api { commands } => commands.debug(),
             SystemOpt::PwBadlist { commands } => commands.debug(),
             SystemOpt::DeniedNames { commands } => commands.debug(),
+            SystemOpt::DeniedPasswordTerms { commands } => commands.debug(),
             SystemOpt::Oauth2 { commands } => commands.debug(),
+            SystemOpt::Webhook { commands } => commands.debug(),
+            SystemOpt::Announcement { commands } => commands.debug(),
+            SystemOpt::OidcUpstream { commands } => commands.debug(),
+            SystemOpt::Saml2 { commands } => commands.debug(),
+            SystemOpt::Hbac { commands } => commands.debug(),
+            SystemOpt::Host { commands } => commands.debug(),
+            SystemOpt::HostGroup { commands } => commands.debug(),
             SystemOpt::Domain { commands } => commands.debug(),
             SystemOpt::Synch { commands } => commands.debug(),
+            SystemOpt::Migrations { commands } => commands.debug(),
+            SystemOpt::AccessControl { commands } => commands.debug(),
         }
     }
 
@@ -162,9 +268,19 @@ impl SystemOpt {
             SystemOpt::Api { commands } => commands.exec().await,
             SystemOpt::PwBadlist { commands } => commands.exec().await,
             SystemOpt::DeniedNames { commands } => commands.exec().await,
+            SystemOpt::DeniedPasswordTerms { commands } => commands.exec().await,
             SystemOpt::Oauth2 { commands } => commands.exec().await,
+            SystemOpt::Webhook { commands } => commands.exec().await,
+            SystemOpt::Announcement { commands } => commands.exec().await,
+            SystemOpt::OidcUpstream { commands } => commands.exec().await,
+            SystemOpt::Saml2 { commands } => commands.exec().await,
+            SystemOpt::Hbac { commands } => commands.exec().await,
+            SystemOpt::Host { commands } => commands.exec().await,
+            SystemOpt::HostGroup { commands } => commands.exec().await,
             SystemOpt::Domain { commands } => commands.exec().await,
             SystemOpt::Synch { commands } => commands.exec().await,
+            SystemOpt::Migrations { commands } => commands.exec().await,
+            SystemOpt::AccessControl { commands } => commands.exec().await,
         }
     }
 }
@@ -173,9 +289,11 @@ impl KanidmClientOpt {
     pub fn debug(&self) -> bool {
         match self {
             KanidmClientOpt::Raw { commands } => commands.debug(),
+            KanidmClientOpt::Debug { commands } => commands.debug(),
             KanidmClientOpt::Login(lopt) => lopt.debug(),
             KanidmClientOpt::Reauth(lopt) => lopt.debug(),
             KanidmClientOpt::Logout(lopt) => lopt.debug(),
+            KanidmClientOpt::BreakGlass { commands } => commands.debug(),
             KanidmClientOpt::Session { commands } => commands.debug(),
             KanidmClientOpt::CSelf { commands } => commands.debug(),
             KanidmClientOpt::Group { commands } => commands.debug(),
@@ -184,6 +302,10 @@ impl KanidmClientOpt {
             KanidmClientOpt::Graph(gopt) => gopt.debug(),
             KanidmClientOpt::System { commands } => commands.debug(),
             KanidmClientOpt::Recycle { commands } => commands.debug(),
+            KanidmClientOpt::Import(iopt) => iopt.debug(),
+            KanidmClientOpt::Export(eopt) => eopt.debug(),
+            KanidmClientOpt::Apply(aopt) => aopt.debug(),
+            KanidmClientOpt::Shell(sopt) => sopt.debug(),
             KanidmClientOpt::Version {} => {
                 println!("kanidm {}", env!("KANIDM_PKG_VERSION"));
                 true
@@ -194,9 +316,11 @@ impl KanidmClientOpt {
     pub async fn exec(&self) {
         match self {
             KanidmClientOpt::Raw { commands } => commands.exec().await,
+            KanidmClientOpt::Debug { commands } => commands.exec().await,
             KanidmClientOpt::Login(lopt) => lopt.exec().await,
             KanidmClientOpt::Reauth(lopt) => lopt.exec().await,
             KanidmClientOpt::Logout(lopt) => lopt.exec().await,
+            KanidmClientOpt::BreakGlass { commands } => commands.exec().await,
             KanidmClientOpt::Session { commands } => commands.exec().await,
             KanidmClientOpt::CSelf { commands } => commands.exec().await,
             KanidmClientOpt::Person { commands } => commands.exec().await,
@@ -205,6 +329,10 @@ impl KanidmClientOpt {
             KanidmClientOpt::Graph(gops) => gops.exec().await,
             KanidmClientOpt::System { commands } => commands.exec().await,
             KanidmClientOpt::Recycle { commands } => commands.exec().await,
+            KanidmClientOpt::Import(iopt) => iopt.exec().await,
+            KanidmClientOpt::Export(eopt) => eopt.exec().await,
+            KanidmClientOpt::Apply(aopt) => aopt.exec().await,
+            KanidmClientOpt::Shell(sopt) => sopt.exec().await,
             KanidmClientOpt::Version {} => (),
         }
     }
@@ -1,11 +1,17 @@
-use crate::common::OpType;
+use crate::common::{try_expire_at_from_string, OpType};
 use crate::{handle_client_error, Oauth2Opt, OutputMode};
 use anyhow::{Context, Error};
 use std::fs::read;
 use std::process::exit;
+use std::str::FromStr;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
 use crate::Oauth2ClaimMapJoin;
-use kanidm_proto::internal::{ImageValue, Oauth2ClaimMapJoin as ProtoOauth2ClaimMapJoin};
+use kanidm_proto::internal::{
+    ImageValue, Oauth2ClaimMapJoin as ProtoOauth2ClaimMapJoin, Oauth2ComputedClaim,
+    Oauth2ScopeDescription, OAUTH2_CLAIM_TEMPLATE_ATTRIBUTES,
+};
 
 impl Oauth2Opt {
     pub fn debug(&self) -> bool {
@@ -16,17 +22,36 @@ impl Oauth2Opt {
             Oauth2Opt::DeleteScopeMap(cbopt) => cbopt.nopt.copt.debug,
             Oauth2Opt::UpdateSupScopeMap(cbopt) => cbopt.nopt.copt.debug,
             Oauth2Opt::DeleteSupScopeMap(cbopt) => cbopt.nopt.copt.debug,
+            Oauth2Opt::AddScopeDescription { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::RemoveScopeDescription { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::AddComputedClaim { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::RemoveComputedClaim { nopt, .. } => nopt.copt.debug,
             Oauth2Opt::ResetSecrets(cbopt) => cbopt.copt.debug,
             // Should this be renamed to show client id? client secrets?
             Oauth2Opt::ShowBasicSecret(nopt) => nopt.copt.debug,
+            Oauth2Opt::RotateBasicSecret { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::RemovePreviousBasicSecret(nopt) => nopt.copt.debug,
+            Oauth2Opt::SetBasicSecretExpiry { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::ListSessions(nopt) => nopt.copt.debug,
             Oauth2Opt::Delete(nopt) => nopt.copt.debug,
             Oauth2Opt::SetDisplayname(cbopt) => cbopt.nopt.copt.debug,
             Oauth2Opt::SetName { nopt, .. } => nopt.copt.debug,
             Oauth2Opt::SetLandingUrl { nopt, .. } => nopt.copt.debug,
             Oauth2Opt::SetImage { nopt, .. } => nopt.copt.debug,
             Oauth2Opt::RemoveImage(nopt) => nopt.copt.debug,
+            Oauth2Opt::SetEntryManagedBy { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::AddTokenExchangeAudience { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::RemoveTokenExchangeAudience { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::AddAssignedMember { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::RemoveAssignedMember { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::AddAllowedGrantType { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::RemoveAllowedGrantType { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::AddAllowedResponseType { nopt, .. } => nopt.copt.debug,
+            Oauth2Opt::RemoveAllowedResponseType { nopt, .. } => nopt.copt.debug,
             Oauth2Opt::EnablePkce(nopt) => nopt.copt.debug,
             Oauth2Opt::DisablePkce(nopt) => nopt.copt.debug,
+            Oauth2Opt::EnableRefreshTokenRotation(nopt) => nopt.copt.debug,
+            Oauth2Opt::DisableRefreshTokenRotation(nopt) => nopt.copt.debug,
             Oauth2Opt::EnableLegacyCrypto(nopt) => nopt.copt.debug,
             Oauth2Opt::DisableLegacyCrypto(nopt) => nopt.copt.debug,
             Oauth2Opt::PreferShortUsername(nopt) => nopt.copt.debug,
@@ -39,6 +64,7 @@ impl Oauth2Opt {
             Oauth2Opt::DeviceFlowEnable(nopt) => nopt.copt.debug,
             Oauth2Opt::CreateBasic { copt, .. }
             | Oauth2Opt::CreatePublic { copt, .. }
+            | Oauth2Opt::CreateNative { copt, .. }
             | Oauth2Opt::UpdateClaimMap { copt, .. }
             | Oauth2Opt::UpdateClaimMapJoin { copt, .. }
             | Oauth2Opt::DeleteClaimMap { copt, .. }
@@ -46,6 +72,10 @@ impl Oauth2Opt {
             | Oauth2Opt::DisablePublicLocalhost { copt, .. }
             | Oauth2Opt::EnableStrictRedirectUri { copt, .. }
             | Oauth2Opt::DisableStrictRedirectUri { copt, .. }
+            | Oauth2Opt::SetRedirectUriMode { copt, .. }
+            | Oauth2Opt::SetTokenSigningAlg { copt, .. }
+            | Oauth2Opt::RotateKeys { copt, .. }
+            | Oauth2Opt::RevokeKey { copt, .. }
             | Oauth2Opt::AddOrigin { copt, .. }
             | Oauth2Opt::RemoveOrigin { copt, .. } => copt.debug,
         }
@@ -139,6 +169,25 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, copt.output_mode),
                 }
             }
+            Oauth2Opt::CreateNative {
+                name,
+                displayname,
+                origin,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_native_create(
+                        name.as_str(),
+                        displayname.as_str(),
+                        origin.as_str(),
+                    )
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
             Oauth2Opt::UpdateScopeMap(cbopt) => {
                 let client = cbopt.nopt.copt.to_client(OpType::Write).await;
                 match client
@@ -193,6 +242,120 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, cbopt.nopt.copt.output_mode),
                 }
             }
+            Oauth2Opt::AddScopeDescription {
+                nopt,
+                scope,
+                title,
+                description,
+                language,
+            } => {
+                let mut value = scope.clone();
+                if let Some(language) = language {
+                    value.push('@');
+                    value.push_str(language);
+                }
+                value.push('=');
+                value.push_str(title);
+                if let Some(description) = description {
+                    value.push('|');
+                    value.push_str(description);
+                }
+
+                let Ok(scope_description) = Oauth2ScopeDescription::from_str(&value) else {
+                    eprintln!("Invalid scope description - the scope and title must not be empty, and the title may not contain '|'");
+                    return;
+                };
+
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_add_scope_description(nopt.name.as_str(), &scope_description)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::RemoveScopeDescription {
+                nopt,
+                scope,
+                language,
+            } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_remove_scope_description(
+                        nopt.name.as_str(),
+                        scope.as_str(),
+                        language.as_deref(),
+                    )
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::AddComputedClaim {
+                nopt,
+                claim,
+                template,
+                groups,
+                filter_prefix,
+                filter_glob,
+                strip_prefix,
+                lowercase,
+                join,
+            } => {
+                let mut value = format!("{claim}=");
+                match template {
+                    Some(template) if !groups => {
+                        value.push_str("template:");
+                        value.push_str(template);
+                    }
+                    _ => value.push_str("groups"),
+                }
+                if let Some(prefix) = filter_prefix {
+                    value.push_str("|filter_prefix:");
+                    value.push_str(prefix);
+                }
+                if let Some(glob) = filter_glob {
+                    value.push_str("|filter_glob:");
+                    value.push_str(glob);
+                }
+                if let Some(prefix) = strip_prefix {
+                    value.push_str("|strip_prefix:");
+                    value.push_str(prefix);
+                }
+                if *lowercase {
+                    value.push_str("|lowercase");
+                }
+                if let Some(join) = join {
+                    value.push_str("|join:");
+                    value.push_str(join);
+                }
+
+                let Ok(computed_claim) = Oauth2ComputedClaim::from_str(&value) else {
+                    eprintln!("Invalid computed claim - the claim name may only contain letters, numbers and '_', templates may not contain '|', and templates may only use the attributes {}", OAUTH2_CLAIM_TEMPLATE_ATTRIBUTES.join(", "));
+                    return;
+                };
+
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_add_computed_claim(nopt.name.as_str(), &computed_claim)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::RemoveComputedClaim { nopt, claim } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_remove_computed_claim(nopt.name.as_str(), claim.as_str())
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
             Oauth2Opt::ResetSecrets(cbopt) => {
                 let client = cbopt.copt.to_client(OpType::Write).await;
                 match client
@@ -222,6 +385,60 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, nopt.copt.output_mode),
                 }
             }
+            Oauth2Opt::RotateBasicSecret {
+                nopt,
+                previous_validity,
+            } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_rotate_basic_secret(nopt.name.as_str(), *previous_validity)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::RemovePreviousBasicSecret(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_remove_previous_basic_secret(nopt.name.as_str())
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::SetBasicSecretExpiry { nopt, datetime } => {
+                let Ok(expiry) = try_expire_at_from_string(datetime.as_str()) else {
+                    return;
+                };
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_set_basic_secret_expiry(nopt.name.as_str(), expiry.as_deref())
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::ListSessions(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client
+                    .idm_oauth2_rs_list_sessions(nopt.name.as_str())
+                    .await
+                {
+                    Ok(sessions) => {
+                        if sessions.is_empty() {
+                            println!("No sessions exist");
+                        } else {
+                            for session in sessions {
+                                println!("session: {}", session);
+                            }
+                        }
+                    }
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
             Oauth2Opt::Delete(nopt) => {
                 let client = nopt.copt.to_client(OpType::Write).await;
                 match client.idm_oauth2_rs_delete(nopt.name.as_str()).await {
@@ -348,6 +565,25 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, nopt.copt.output_mode),
                 }
             }
+            Oauth2Opt::SetEntryManagedBy {
+                nopt,
+                entry_managed_by,
+            } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_set_entry_managed_by(
+                        nopt.name.as_str(),
+                        entry_managed_by.as_str(),
+                    )
+                    .await
+                {
+                    Ok(_) => println!(
+                        "Successfully set entry manager to '{}' for oauth2 client '{}'",
+                        entry_managed_by, nopt.name
+                    ),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
             Oauth2Opt::EnablePkce(nopt) => {
                 let client = nopt.copt.to_client(OpType::Write).await;
                 match client.idm_oauth2_rs_enable_pkce(nopt.name.as_str()).await {
@@ -362,6 +598,26 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, nopt.copt.output_mode),
                 }
             }
+            Oauth2Opt::EnableRefreshTokenRotation(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_enable_refresh_token_rotation(nopt.name.as_str())
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::DisableRefreshTokenRotation(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_disable_refresh_token_rotation(nopt.name.as_str())
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
             Oauth2Opt::EnableLegacyCrypto(nopt) => {
                 let client = nopt.copt.to_client(OpType::Write).await;
                 match client
@@ -417,6 +673,95 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, copt.output_mode),
                 }
             }
+            Oauth2Opt::AddTokenExchangeAudience { nopt, audience } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_add_token_exchange_audience(nopt.name.as_str(), audience)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::RemoveTokenExchangeAudience { nopt, audience } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_remove_token_exchange_audience(nopt.name.as_str(), audience)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::AddAssignedMember { nopt, member } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_add_assigned_member(nopt.name.as_str(), member)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::RemoveAssignedMember { nopt, member } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_remove_assigned_member(nopt.name.as_str(), member)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::AddAllowedGrantType { nopt, grant_type } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_add_allowed_grant_type(nopt.name.as_str(), *grant_type)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::RemoveAllowedGrantType { nopt, grant_type } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_remove_allowed_grant_type(nopt.name.as_str(), *grant_type)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::AddAllowedResponseType {
+                nopt,
+                response_type,
+            } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_add_allowed_response_type(nopt.name.as_str(), *response_type)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Oauth2Opt::RemoveAllowedResponseType {
+                nopt,
+                response_type,
+            } => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_client_remove_allowed_response_type(
+                        nopt.name.as_str(),
+                        *response_type,
+                    )
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
             Oauth2Opt::UpdateClaimMap {
                 copt,
                 name,
@@ -522,6 +867,72 @@ impl Oauth2Opt {
                     Err(e) => handle_client_error(e, copt.output_mode),
                 }
             }
+
+            Oauth2Opt::SetRedirectUriMode { copt, name, mode } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_set_redirect_uri_mode(name.as_str(), *mode)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            Oauth2Opt::SetTokenSigningAlg {
+                copt,
+                name,
+                sign_alg,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_set_jwt_sign_alg(name.as_str(), *sign_alg)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            Oauth2Opt::RotateKeys {
+                copt,
+                name,
+                rotate_at,
+            } => {
+                let rotate_at = match rotate_at {
+                    Some(t) => match OffsetDateTime::parse(t, &Rfc3339) {
+                        Ok(odt) => odt,
+                        Err(err) => {
+                            error!(?err, "Unable to parse rotate-at as an RFC3339 timestamp");
+                            return;
+                        }
+                    },
+                    None => OffsetDateTime::now_utc(),
+                };
+                let rotate_at = match rotate_at.format(&Rfc3339) {
+                    Ok(t) => t,
+                    Err(err) => {
+                        error!(?err, "Unable to format rotate-at");
+                        return;
+                    }
+                };
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_rotate_keys(name.as_str(), rotate_at.as_str())
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            Oauth2Opt::RevokeKey { copt, name, key_id } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oauth2_rs_revoke_key(name.as_str(), key_id.as_str())
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
         }
     }
 }
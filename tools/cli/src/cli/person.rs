@@ -7,13 +7,17 @@ use dialoguer::{Confirm, Input, Password, Select};
 use kanidm_client::ClientError::Http as ClientErrorHttp;
 use kanidm_client::KanidmClient;
 use kanidm_proto::attribute::Attribute;
-use kanidm_proto::constants::{ATTR_ACCOUNT_EXPIRE, ATTR_ACCOUNT_VALID_FROM, ATTR_GIDNUMBER};
+use kanidm_proto::constants::{
+    ATTR_ACCOUNT_EXPIRE, ATTR_ACCOUNT_VALID_FROM, ATTR_GIDNUMBER, ATTR_NOTIFICATION_OPT_OUT,
+    ATTR_PASSWORD_IMPORT, ATTR_POSIX_HOME_QUOTA, ATTR_SPONSOR,
+};
 use kanidm_proto::internal::OperationError::{
-    DuplicateKey, DuplicateLabel, InvalidLabel, NoMatchingEntries, PasswordQuality,
+    DuplicateKey, DuplicateLabel, InvalidLabel, NoMatchingEntries, PL0003SshPublicKeyPolicyDenied,
+    PasswordQuality,
 };
 use kanidm_proto::internal::{
-    CUCredState, CUExtPortal, CUIntentToken, CURegState, CURegWarning, CUSessionToken, CUStatus,
-    SshPublicKey, TotpSecret,
+    CUCredState, CUExtPortal, CUIntentToken, CUIntentTokenState, CURegState, CURegWarning,
+    CUSessionToken, CUStatus, SshPublicKey, TotpSecret,
 };
 use kanidm_proto::internal::{CredentialDetail, CredentialDetailType};
 use kanidm_proto::messages::{AccountChangeMessage, ConsoleOutputMode, MessageStatus};
@@ -27,7 +31,8 @@ use uuid::Uuid;
 use crate::webauthn::get_authenticator;
 use crate::{
     handle_client_error, password_prompt, AccountCertificate, AccountCredential, AccountRadius,
-    AccountSsh, AccountUserAuthToken, AccountValidity, OutputMode, PersonOpt, PersonPosix,
+    AccountSsh, AccountUserAuthToken, AccountValidity, OutputMode, PersonContractor,
+    PersonNotification, PersonOpt, PersonPosix,
 };
 
 impl PersonOpt {
@@ -38,6 +43,8 @@ impl PersonOpt {
                 AccountRadius::Show(aro) => aro.copt.debug,
                 AccountRadius::Generate(aro) => aro.copt.debug,
                 AccountRadius::DeleteSecret(aro) => aro.copt.debug,
+                AccountRadius::IssueCertificate { copt, .. } => copt.debug,
+                AccountRadius::ShowCaCertificate { copt } => copt.debug,
             },
             PersonOpt::Posix { commands } => match commands {
                 PersonPosix::Show(apo) => apo.copt.debug,
@@ -47,12 +54,14 @@ impl PersonOpt {
             },
             PersonOpt::Session { commands } => match commands {
                 AccountUserAuthToken::Status(apo) => apo.copt.debug,
+                AccountUserAuthToken::Oauth2Status(apo) => apo.copt.debug,
                 AccountUserAuthToken::Destroy { copt, .. } => copt.debug,
             },
             PersonOpt::Ssh { commands } => match commands {
                 AccountSsh::List(ano) => ano.copt.debug,
                 AccountSsh::Add(ano) => ano.copt.debug,
                 AccountSsh::Delete(ano) => ano.copt.debug,
+                AccountSsh::SetExpiry(ano) => ano.copt.debug,
             },
             PersonOpt::List(copt) => copt.debug,
             PersonOpt::Get(aopt) => aopt.copt.debug,
@@ -64,6 +73,18 @@ impl PersonOpt {
                 AccountValidity::ExpireAt(ano) => ano.copt.debug,
                 AccountValidity::BeginFrom(ano) => ano.copt.debug,
             },
+            PersonOpt::Contractor { commands } => match commands {
+                PersonContractor::Create(ccopt) => ccopt.copt.debug,
+                PersonContractor::GetSponsor(aopt) => aopt.copt.debug,
+                PersonContractor::SetSponsor { copt, .. } => copt.debug,
+            },
+            PersonOpt::Notification { commands } => match commands {
+                PersonNotification::Show(aopt) => aopt.copt.debug,
+                PersonNotification::OptOut { copt, .. }
+                | PersonNotification::OptIn { copt, .. } => copt.debug,
+            },
+            PersonOpt::AccountPolicy(aopt) => aopt.copt.debug,
+            PersonOpt::Impersonate { copt, .. } => copt.debug,
             PersonOpt::Certificate { commands } => match commands {
                 AccountCertificate::Status { copt, .. }
                 | AccountCertificate::Create { copt, .. } => copt.debug,
@@ -135,6 +156,35 @@ impl PersonOpt {
                         }
                     };
                 }
+                AccountRadius::IssueCertificate {
+                    account_id,
+                    csr_path,
+                    copt,
+                } => {
+                    let csr = match tokio::fs::read_to_string(csr_path).await {
+                        Ok(csr) => csr,
+                        Err(io_err) => {
+                            error!(?io_err, ?csr_path, "Unable to read PEM data");
+                            return;
+                        }
+                    };
+
+                    let client = copt.to_client(OpType::Write).await;
+                    match client
+                        .idm_account_radius_certificate_issue(account_id, &csr)
+                        .await
+                    {
+                        Ok(cert) => print!("{}", cert),
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
+                AccountRadius::ShowCaCertificate { copt } => {
+                    let client = copt.to_client(OpType::Read).await;
+                    match client.idm_radius_ca_certificate_get().await {
+                        Ok(cert) => print!("{}", cert),
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
             }, // end PersonOpt::Radius
             PersonOpt::Posix { commands } => match commands {
                 PersonPosix::Show(aopt) => {
@@ -158,6 +208,17 @@ impl PersonOpt {
                         .await
                     {
                         handle_client_error(e, aopt.copt.output_mode)
+                    } else if let Some(home_quota) = aopt.home_quota {
+                        if let Err(e) = client
+                            .idm_person_account_set_attr(
+                                aopt.aopts.account_id.as_str(),
+                                ATTR_POSIX_HOME_QUOTA,
+                                &[home_quota.to_string().as_str()],
+                            )
+                            .await
+                        {
+                            handle_client_error(e, aopt.copt.output_mode)
+                        }
                     }
                 }
                 PersonPosix::SetPassword(aopt) => {
@@ -209,6 +270,24 @@ impl PersonOpt {
                         Err(e) => handle_client_error(e, apo.copt.output_mode),
                     }
                 }
+                AccountUserAuthToken::Oauth2Status(apo) => {
+                    let client = apo.copt.to_client(OpType::Read).await;
+                    match client
+                        .idm_account_list_oauth2_sessions(apo.aopts.account_id.as_str())
+                        .await
+                    {
+                        Ok(sessions) => {
+                            if sessions.is_empty() {
+                                println!("No sessions exist");
+                            } else {
+                                for session in sessions {
+                                    println!("session: {}", session);
+                                }
+                            }
+                        }
+                        Err(e) => handle_client_error(e, apo.copt.output_mode),
+                    }
+                }
                 AccountUserAuthToken::Destroy {
                     aopts,
                     copt,
@@ -286,6 +365,26 @@ impl PersonOpt {
                         handle_client_error(e, aopt.copt.output_mode)
                     }
                 }
+                AccountSsh::SetExpiry(aopt) => {
+                    let client = aopt.copt.to_client(OpType::Write).await;
+                    let expiry = match try_expire_at_from_string(aopt.datetime.as_str()) {
+                        Ok(expiry) => {
+                            expiry.and_then(|expiry| OffsetDateTime::parse(&expiry, &Rfc3339).ok())
+                        }
+                        Err(()) => return,
+                    };
+                    match client
+                        .idm_person_account_set_ssh_pubkey_expiry(
+                            aopt.aopts.account_id.as_str(),
+                            aopt.tag.as_str(),
+                            expiry,
+                        )
+                        .await
+                    {
+                        Err(e) => handle_client_error(e, aopt.copt.output_mode),
+                        _ => println!("Success"),
+                    }
+                }
             }, // end PersonOpt::Ssh
             PersonOpt::List(copt) => {
                 let client = copt.to_client(OpType::Read).await;
@@ -354,6 +453,57 @@ impl PersonOpt {
                     Err(e) => handle_client_error(e, aopt.copt.output_mode),
                 }
             }
+            PersonOpt::AccountPolicy(aopt) => {
+                let client = aopt.copt.to_client(OpType::Read).await;
+                match client
+                    .idm_account_get_account_policy(aopt.aopts.account_id.as_str())
+                    .await
+                {
+                    Ok(policy) => match aopt.copt.output_mode {
+                        OutputMode::Json => {
+                            println!(
+                                "{}",
+                                serde_json::to_string(&policy).expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => print!("{}", policy),
+                    },
+                    Err(e) => handle_client_error(e, aopt.copt.output_mode),
+                }
+            }
+            PersonOpt::Impersonate {
+                aopts,
+                copt,
+                expiry,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_person_impersonate(aopts.account_id.as_str(), *expiry)
+                    .await
+                {
+                    Ok(token) => match copt.output_mode {
+                        OutputMode::Json => {
+                            let message = AccountChangeMessage {
+                                output_mode: ConsoleOutputMode::JSON,
+                                action: "impersonate".to_string(),
+                                result: token,
+                                status: MessageStatus::Success,
+                                src_user: copt
+                                    .username
+                                    .clone()
+                                    .unwrap_or("<unknown username>".to_string()),
+                                dest_user: aopts.account_id.clone(),
+                            };
+                            println!("{}", message);
+                        }
+                        OutputMode::Text => {
+                            println!("Success: This session is read only, and will only be displayed ONCE");
+                            println!("{}", token)
+                        }
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
             PersonOpt::Delete(aopt) => {
                 let client = aopt.copt.to_client(OpType::Write).await;
                 let mut modmessage = AccountChangeMessage {
@@ -537,6 +687,160 @@ impl PersonOpt {
                     }
                 }
             }, // end PersonOpt::Validity
+            PersonOpt::Contractor { commands } => match commands {
+                PersonContractor::Create(ccopt) => {
+                    // A contractor must always have an expiry, so the values that clear it
+                    // are rejected here rather than by the server.
+                    let expiry = match try_expire_at_from_string(ccopt.datetime.as_str()) {
+                        Ok(Some(val)) => val,
+                        Ok(None) => {
+                            error!("A contractor must have an expiry time");
+                            return;
+                        }
+                        Err(()) => return,
+                    };
+                    let client = ccopt.copt.to_client(OpType::Write).await;
+                    match client
+                        .idm_person_contractor_create(
+                            ccopt.aopts.account_id.as_str(),
+                            ccopt.display_name.as_str(),
+                            ccopt.sponsor.as_str(),
+                            expiry.as_str(),
+                        )
+                        .await
+                    {
+                        Ok(_) => {
+                            println!(
+                                "Successfully created display_name=\"{}\" username={} sponsor={} expiry={}",
+                                ccopt.display_name.as_str(),
+                                ccopt.aopts.account_id.as_str(),
+                                ccopt.sponsor.as_str(),
+                                expiry,
+                            )
+                        }
+                        Err(e) => handle_client_error(e, ccopt.copt.output_mode),
+                    }
+                }
+                PersonContractor::GetSponsor(aopt) => {
+                    let client = aopt.copt.to_client(OpType::Read).await;
+                    match client
+                        .idm_person_account_get_attr(aopt.aopts.account_id.as_str(), ATTR_SPONSOR)
+                        .await
+                    {
+                        Ok(Some(sponsor)) => println!("sponsor: {}", sponsor.join(", ")),
+                        Ok(None) => println!("No sponsor set for {}", aopt.aopts.account_id),
+                        Err(e) => handle_client_error(e, aopt.copt.output_mode),
+                    }
+                }
+                PersonContractor::SetSponsor {
+                    aopts,
+                    sponsor,
+                    copt,
+                } => {
+                    let client = copt.to_client(OpType::Write).await;
+                    match client
+                        .idm_person_account_set_attr(
+                            aopts.account_id.as_str(),
+                            ATTR_SPONSOR,
+                            &[sponsor.as_str()],
+                        )
+                        .await
+                    {
+                        Ok(_) => println!("Success"),
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
+            }, // end PersonOpt::Contractor
+            PersonOpt::Notification { commands } => match commands {
+                PersonNotification::Show(aopt) => {
+                    let client = aopt.copt.to_client(OpType::Read).await;
+                    match client
+                        .idm_person_account_get_attr(
+                            aopt.aopts.account_id.as_str(),
+                            ATTR_NOTIFICATION_OPT_OUT,
+                        )
+                        .await
+                    {
+                        Ok(Some(opt_out)) => println!("opted out of: {}", opt_out.join(", ")),
+                        Ok(None) => println!(
+                            "{} receives all security notifications",
+                            aopt.aopts.account_id
+                        ),
+                        Err(e) => handle_client_error(e, aopt.copt.output_mode),
+                    }
+                }
+                PersonNotification::OptOut {
+                    aopts,
+                    category,
+                    copt,
+                } => {
+                    let client = copt.to_client(OpType::Write).await;
+                    match client
+                        .idm_person_account_add_attr(
+                            aopts.account_id.as_str(),
+                            ATTR_NOTIFICATION_OPT_OUT,
+                            &[category.as_str()],
+                        )
+                        .await
+                    {
+                        Ok(_) => println!("Success"),
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
+                PersonNotification::OptIn {
+                    aopts,
+                    category,
+                    copt,
+                } => {
+                    let client = copt.to_client(OpType::Write).await;
+                    let opt_out = match client
+                        .idm_person_account_get_attr(
+                            aopts.account_id.as_str(),
+                            ATTR_NOTIFICATION_OPT_OUT,
+                        )
+                        .await
+                    {
+                        Ok(opt_out) => opt_out.unwrap_or_default(),
+                        Err(e) => {
+                            handle_client_error(e, copt.output_mode);
+                            return;
+                        }
+                    };
+
+                    let remaining: Vec<&str> = opt_out
+                        .iter()
+                        .map(String::as_str)
+                        .filter(|c| !c.eq_ignore_ascii_case(category))
+                        .collect();
+
+                    if remaining.len() == opt_out.len() {
+                        println!("{} is not opted out of {}", aopts.account_id, category);
+                        return;
+                    }
+
+                    let result = if remaining.is_empty() {
+                        client
+                            .idm_person_account_purge_attr(
+                                aopts.account_id.as_str(),
+                                ATTR_NOTIFICATION_OPT_OUT,
+                            )
+                            .await
+                    } else {
+                        client
+                            .idm_person_account_set_attr(
+                                aopts.account_id.as_str(),
+                                ATTR_NOTIFICATION_OPT_OUT,
+                                &remaining,
+                            )
+                            .await
+                    };
+
+                    match result {
+                        Ok(_) => println!("Success"),
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
+            }, // end PersonOpt::Notification
             PersonOpt::Certificate { commands } => commands.exec().await,
         }
     }
@@ -599,7 +903,12 @@ impl AccountCredential {
     pub fn debug(&self) -> bool {
         match self {
             AccountCredential::Status(aopt) => aopt.copt.debug,
+            AccountCredential::SoftlockStatus(aopt) => aopt.copt.debug,
+            AccountCredential::SoftlockClear(aopt) => aopt.copt.debug,
             AccountCredential::CreateResetToken { copt, .. } => copt.debug,
+            AccountCredential::ListResetTokens(aopt) => aopt.copt.debug,
+            AccountCredential::RevokeResetToken { copt, .. } => copt.debug,
+            AccountCredential::ImportHash { copt, .. } => copt.debug,
             AccountCredential::UseResetToken(aopt) => aopt.copt.debug,
             AccountCredential::Update(aopt) => aopt.copt.debug,
         }
@@ -621,6 +930,31 @@ impl AccountCredential {
                     }
                 }
             }
+            AccountCredential::SoftlockStatus(aopt) => {
+                let client = aopt.copt.to_client(OpType::Read).await;
+                match client
+                    .idm_person_account_get_credential_softlock(aopt.aopts.account_id.as_str())
+                    .await
+                {
+                    Ok(slstatus) => {
+                        println!("{}", slstatus);
+                    }
+                    Err(e) => {
+                        error!("Error getting credential softlock status -> {:?}", e);
+                    }
+                }
+            }
+            AccountCredential::SoftlockClear(aopt) => {
+                let client = aopt.copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .idm_person_account_clear_credential_softlock(aopt.aopts.account_id.as_str())
+                    .await
+                {
+                    handle_client_error(e, aopt.copt.output_mode);
+                } else {
+                    println!("Success");
+                }
+            }
             AccountCredential::Update(aopt) => {
                 let client = aopt.copt.to_client(OpType::Write).await;
                 match client
@@ -660,15 +994,58 @@ impl AccountCredential {
                     }
                 }
             }
-            AccountCredential::CreateResetToken { aopts, copt, ttl } => {
+            AccountCredential::CreateResetToken {
+                aopts,
+                copt,
+                ttl,
+                max_uses,
+                send_mail,
+            } => {
                 let client = copt.to_client(OpType::Write).await;
 
+                let intent = if max_uses.is_some() || *send_mail {
+                    client
+                        .idm_person_account_credential_create_reset_token(
+                            aopts.account_id.as_str(),
+                            ttl.map(u64::from),
+                            *max_uses,
+                            *send_mail,
+                        )
+                        .await
+                } else {
+                    client
+                        .idm_person_account_credential_update_intent(
+                            aopts.account_id.as_str(),
+                            *ttl,
+                        )
+                        .await
+                };
+
                 // What's the client url?
-                match client
-                    .idm_person_account_credential_update_intent(aopts.account_id.as_str(), *ttl)
-                    .await
-                {
-                    Ok(CUIntentToken { token, expiry_time }) => {
+                match intent {
+                    Ok(CUIntentToken {
+                        expiry_time,
+                        mailed_to: Some(mail),
+                        ..
+                    }) => {
+                        let local_offset =
+                            UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+                        let expiry_time = expiry_time.to_offset(local_offset);
+
+                        println!("The reset token was sent to {}", mail);
+                        println!(
+                            "This token will expire at: {}",
+                            expiry_time
+                                .format(&Rfc3339)
+                                .expect("Failed to format date time!!!")
+                        );
+                    }
+                    Ok(CUIntentToken {
+                        token,
+                        expiry_time,
+                        max_uses,
+                        mailed_to: None,
+                    }) => {
                         let mut url = client.make_url("/ui/reset");
                         url.query_pairs_mut().append_pair("token", token.as_str());
 
@@ -712,6 +1089,9 @@ impl AccountCredential {
                                 .format(&Rfc3339)
                                 .expect("Failed to format date time!!!")
                         );
+                        if max_uses > 1 {
+                            println!("This token may be used {} times.", max_uses);
+                        }
                         println!();
                     }
                     Err(e) => {
@@ -719,6 +1099,89 @@ impl AccountCredential {
                     }
                 }
             }
+            AccountCredential::ListResetTokens(aopt) => {
+                let client = aopt.copt.to_client(OpType::Read).await;
+                match client
+                    .idm_person_account_credential_list_reset_tokens(aopt.aopts.account_id.as_str())
+                    .await
+                {
+                    Ok(intents) => {
+                        if intents.is_empty() {
+                            println!("No reset tokens");
+                        }
+                        let local_offset =
+                            UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+                        for intent in intents {
+                            let state = match intent.state {
+                                CUIntentTokenState::Valid { uses_remaining } => {
+                                    format!("valid ({} uses remaining)", uses_remaining)
+                                }
+                                CUIntentTokenState::InProgress { uses_remaining } => {
+                                    format!("in use ({} uses remaining)", uses_remaining)
+                                }
+                                CUIntentTokenState::Consumed => "consumed".to_string(),
+                            };
+                            println!("token_ref: {}", intent.token_ref);
+                            println!("state: {}", state);
+                            println!(
+                                "expires: {}",
+                                intent
+                                    .expiry_time
+                                    .to_offset(local_offset)
+                                    .format(&Rfc3339)
+                                    .expect("Failed to format date time!!!")
+                            );
+                            println!();
+                        }
+                    }
+                    Err(e) => handle_client_error(e, aopt.copt.output_mode),
+                }
+            }
+            AccountCredential::ImportHash { aopts, copt, hash } => {
+                let hash = match hash {
+                    Some(hash) => hash.clone(),
+                    None => {
+                        let mut hash = String::new();
+                        if let Err(e) = std::io::stdin().read_line(&mut hash) {
+                            error!("Failed to read from stdin -> {:?}", e);
+                            return;
+                        }
+                        hash
+                    }
+                };
+
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .idm_person_account_add_attr(
+                        aopts.account_id.as_str(),
+                        ATTR_PASSWORD_IMPORT,
+                        &[hash.trim()],
+                    )
+                    .await
+                {
+                    handle_client_error(e, copt.output_mode);
+                } else {
+                    println!("Success");
+                }
+            }
+            AccountCredential::RevokeResetToken {
+                aopts,
+                copt,
+                token_ref,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                if let Err(e) = client
+                    .idm_person_account_credential_revoke_reset_token(
+                        aopts.account_id.as_str(),
+                        token_ref.as_str(),
+                    )
+                    .await
+                {
+                    handle_client_error(e, copt.output_mode);
+                } else {
+                    println!("Success");
+                }
+            }
         }
     }
 }
@@ -1234,6 +1697,11 @@ async fn sshkey_add_prompt(session_token: &CUSessionToken, client: &KanidmClient
                 ClientErrorHttp(_, Some(DuplicateKey), _) => {
                     eprintln!("SSH Public Key already exists in this account");
                 }
+                ClientErrorHttp(_, Some(PL0003SshPublicKeyPolicyDenied), _) => {
+                    eprintln!(
+                        "SSH Public Key type or length is not permitted by the account policy"
+                    );
+                }
                 _ => eprintln!("An error occurred -> {:?}", err),
             }
             break;
@@ -1293,6 +1761,9 @@ fn display_warnings(warnings: &[CURegWarning]) {
             CURegWarning::WebauthnAttestationUnsatisfiable => {
                 println!("Attestation is unsatisfiable. Contact your administrator.");
             }
+            CURegWarning::WebauthnAttestationNotTrusted => {
+                println!("The device you attempted to register is not approved for your account. Use a device approved by your administrator.");
+            }
             CURegWarning::Unsatisfiable => {
                 println!("Account policy is unsatisfiable. Contact your administrator.");
             }
@@ -1324,11 +1795,27 @@ fn display_status(status: CUStatus) {
         unixcred_state,
         sshkeys,
         sshkeys_state,
+        intent_expiry_time,
+        intent_uses_remaining,
     } = status;
 
     println!("spn: {}", spn);
     println!("Name: {}", displayname);
 
+    if let Some(expiry_time) = intent_expiry_time {
+        let local_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+        let expiry_time = expiry_time.to_offset(local_offset);
+        println!(
+            "Reset token valid until: {}",
+            expiry_time
+                .format(&Rfc3339)
+                .unwrap_or_else(|_| expiry_time.to_string())
+        );
+    }
+    if let Some(uses) = intent_uses_remaining {
+        println!("Reset token uses remaining: {}", uses);
+    }
+
     match ext_cred_portal {
         CUExtPortal::None => {}
         CUExtPortal::Hidden => {
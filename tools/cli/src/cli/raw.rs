@@ -5,7 +5,7 @@ use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
 
-use kanidm_proto::internal::{Filter, Modify, ModifyList};
+use kanidm_proto::internal::{DryRunResponse, Filter, Modify, ModifyList};
 use kanidm_proto::v1::Entry;
 use serde::de::DeserializeOwned;
 
@@ -18,6 +18,25 @@ fn read_file<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, Box<dyn
     Ok(serde_json::from_reader(r)?)
 }
 
+fn print_dry_run(response: &DryRunResponse, output_mode: OutputMode) {
+    match output_mode {
+        #[allow(clippy::expect_used)]
+        OutputMode::Json => {
+            println!(
+                "{}",
+                serde_json::to_string(response).expect("Failed to serialize entry!")
+            )
+        }
+        OutputMode::Text => {
+            response.entries.iter().for_each(|e| println!("{}", e));
+            println!(
+                "dry run: {} entries checked, no changes were made",
+                response.entries.len()
+            );
+        }
+    }
+}
+
 impl RawOpt {
     pub fn debug(&self) -> bool {
         match self {
@@ -70,7 +89,12 @@ impl RawOpt {
 
                 let entries = r_entries.into_iter().map(|b| Entry { attrs: b }).collect();
 
-                if let Err(e) = client.create(entries).await {
+                if copt.dry_run {
+                    match client.create_dry_run(entries).await {
+                        Ok(response) => print_dry_run(&response, copt.commonopts.output_mode),
+                        Err(e) => error!("Error -> {:?}", e),
+                    }
+                } else if let Err(e) = client.create(entries).await {
                     error!("Error -> {:?}", e);
                 }
             }
@@ -94,7 +118,12 @@ impl RawOpt {
                 };
 
                 let modlist = ModifyList::new_list(r_list);
-                if let Err(e) = client.modify(filter, modlist).await {
+                if mopt.dry_run {
+                    match client.modify_dry_run(filter, modlist).await {
+                        Ok(response) => print_dry_run(&response, mopt.commonopts.output_mode),
+                        Err(e) => error!("Error -> {:?}", e),
+                    }
+                } else if let Err(e) = client.modify(filter, modlist).await {
                     error!("Error -> {:?}", e);
                 }
             }
@@ -108,7 +137,12 @@ impl RawOpt {
                     }
                 };
 
-                if let Err(e) = client.delete(filter).await {
+                if dopt.dry_run {
+                    match client.delete_dry_run(filter).await {
+                        Ok(response) => print_dry_run(&response, dopt.commonopts.output_mode),
+                        Err(e) => error!("Error -> {:?}", e),
+                    }
+                } else if let Err(e) = client.delete(filter).await {
                     error!("Error -> {:?}", e);
                 }
             }
@@ -1,11 +1,76 @@
 use crate::common::OpType;
-use crate::{handle_client_error, RecycleOpt};
+use crate::debug::{cid_time, last_changed_by};
+use crate::{handle_client_error, OutputMode, RecycleOpt};
+use kanidm_proto::internal::{EntryHistoryResponse, EntryInspectState};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+fn display_recycled(history: &EntryHistoryResponse, at: Option<OffsetDateTime>) {
+    let entry = &history.entry;
+    let changed_by = last_changed_by(history);
+
+    println!("uuid: {}", entry.uuid);
+    println!("recycled at: {}", entry.state_at);
+    println!("attributes:");
+    for (attr, a) in entry.attrs.iter() {
+        let changed_time = a.changed_at.as_deref().and_then(cid_time);
+        let mut notes = Vec::with_capacity(2);
+
+        if let Some(changed_time) = changed_time {
+            notes.push(format!(
+                "last changed {}",
+                changed_time.format(&Rfc3339).unwrap_or_default()
+            ));
+            if at.is_some_and(|at| changed_time > at) {
+                notes.push("changed since the requested time".to_string());
+            }
+        }
+        if let Some(actor) = changed_by.get(attr.as_str()) {
+            notes.push(format!("by {}", actor));
+        }
+
+        if notes.is_empty() {
+            println!("  {}:", attr);
+        } else {
+            println!("  {} ({}):", attr, notes.join(", "));
+        }
+        a.values.iter().for_each(|v| println!("    {}", v));
+    }
+
+    if let Some(at) = at {
+        // Only the current value of each attribute is retained, so the values before a
+        // change can not be shown. The audit log shows which changes were made since.
+        println!("changes since {}:", at.format(&Rfc3339).unwrap_or_default());
+        history
+            .events
+            .iter()
+            .filter(|event| {
+                OffsetDateTime::parse(&event.time, &Rfc3339)
+                    .map(|t| t > at)
+                    .unwrap_or(true)
+            })
+            .for_each(|event| {
+                let actor = event
+                    .actor
+                    .map(|actor| format!(" by {}", actor))
+                    .unwrap_or_default();
+                println!(
+                    "  {} {}{} {}",
+                    event.time,
+                    event.event,
+                    actor,
+                    event.attrs.join(", ")
+                );
+            });
+    }
+}
 
 impl RecycleOpt {
     pub fn debug(&self) -> bool {
         match self {
             RecycleOpt::List(copt) => copt.debug,
             RecycleOpt::Get(nopt) => nopt.copt.debug,
+            RecycleOpt::Inspect(iopt) => iopt.copt.debug,
             RecycleOpt::Revive(nopt) => nopt.copt.debug,
         }
     }
@@ -27,6 +92,33 @@ impl RecycleOpt {
                     Err(e) => handle_client_error(e, nopt.copt.output_mode),
                 }
             }
+            RecycleOpt::Inspect(iopt) => {
+                let at = match &iopt.at {
+                    Some(t) => match OffsetDateTime::parse(t, &Rfc3339) {
+                        Ok(at) => Some(at),
+                        Err(e) => {
+                            error!("Invalid time, expected RFC3339 format -> {:?}", e);
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+
+                let client = iopt.copt.to_client(OpType::Read).await;
+                match client.entry_history(iopt.name.as_str()).await {
+                    Ok(history) if history.entry.state != EntryInspectState::Recycled => {
+                        error!("{} is not in the recycle bin", iopt.name);
+                    }
+                    Ok(history) => match iopt.copt.output_mode {
+                        OutputMode::Json => println!(
+                            "{}",
+                            serde_json::to_string(&history).expect("Failed to serialise json")
+                        ),
+                        OutputMode::Text => display_recycled(&history, at),
+                    },
+                    Err(e) => handle_client_error(e, iopt.copt.output_mode),
+                }
+            }
             RecycleOpt::Revive(nopt) => {
                 let client = nopt.copt.to_client(OpType::Write).await;
                 if let Err(e) = client.recycle_bin_revive(nopt.name.as_str()).await {
@@ -1,13 +1,17 @@
 use crate::common::{try_expire_at_from_string, OpType};
 use kanidm_proto::constants::{
-    ATTR_ACCOUNT_EXPIRE, ATTR_ACCOUNT_VALID_FROM, ATTR_GIDNUMBER, ATTR_SSH_PUBLICKEY,
+    ATTR_ACCOUNT_EXPIRE, ATTR_ACCOUNT_VALID_FROM, ATTR_GIDNUMBER, ATTR_POSIX_HOME_QUOTA,
+    ATTR_SSH_PUBLICKEY,
 };
+use kanidm_proto::internal::ApiTokenPermission;
 use kanidm_proto::messages::{AccountChangeMessage, ConsoleOutputMode, MessageStatus};
+use std::str::FromStr;
 use time::OffsetDateTime;
 
 use crate::{
     handle_client_error, AccountSsh, AccountUserAuthToken, AccountValidity, OutputMode,
-    ServiceAccountApiToken, ServiceAccountCredential, ServiceAccountOpt, ServiceAccountPosix,
+    ServiceAccountApiToken, ServiceAccountCredential, ServiceAccountJwtKey, ServiceAccountOpt,
+    ServiceAccountPosix,
 };
 use time::format_description::well_known::Rfc3339;
 
@@ -23,6 +27,11 @@ impl ServiceAccountOpt {
                 ServiceAccountApiToken::Generate { copt, .. } => copt.debug,
                 ServiceAccountApiToken::Destroy { copt, .. } => copt.debug,
             },
+            ServiceAccountOpt::JwtKey { commands } => match commands {
+                ServiceAccountJwtKey::List(apo) => apo.copt.debug,
+                ServiceAccountJwtKey::Add { copt, .. } => copt.debug,
+                ServiceAccountJwtKey::Remove { copt, .. } => copt.debug,
+            },
             ServiceAccountOpt::Posix { commands } => match commands {
                 ServiceAccountPosix::Show(apo) => apo.copt.debug,
                 ServiceAccountPosix::Set(apo) => apo.copt.debug,
@@ -30,12 +39,14 @@ impl ServiceAccountOpt {
             },
             ServiceAccountOpt::Session { commands } => match commands {
                 AccountUserAuthToken::Status(apo) => apo.copt.debug,
+                AccountUserAuthToken::Oauth2Status(apo) => apo.copt.debug,
                 AccountUserAuthToken::Destroy { copt, .. } => copt.debug,
             },
             ServiceAccountOpt::Ssh { commands } => match commands {
                 AccountSsh::List(ano) => ano.copt.debug,
                 AccountSsh::Add(ano) => ano.copt.debug,
                 AccountSsh::Delete(ano) => ano.copt.debug,
+                AccountSsh::SetExpiry(ano) => ano.copt.debug,
             },
             ServiceAccountOpt::List(copt) => copt.debug,
             ServiceAccountOpt::Get(aopt) => aopt.copt.debug,
@@ -110,7 +121,20 @@ impl ServiceAccountOpt {
                     label,
                     expiry,
                     read_write,
+                    permissions,
                 } => {
+                    let permissions = match permissions
+                        .iter()
+                        .map(|p| ApiTokenPermission::from_str(p).map_err(|_| p))
+                        .collect::<Result<Vec<_>, _>>()
+                    {
+                        Ok(permissions) => permissions,
+                        Err(p) => {
+                            error!("Invalid permission {p:?} - the form is class[:name_prefix]=attr[,attr]...");
+                            return;
+                        }
+                    };
+
                     let expiry_odt = if let Some(t) = expiry {
                         // Convert the time to local timezone.
                         match OffsetDateTime::parse(t, &Rfc3339).map(|odt| {
@@ -134,15 +158,27 @@ impl ServiceAccountOpt {
 
                     let client = copt.to_client(OpType::Write).await;
 
-                    match client
-                        .idm_service_account_generate_api_token(
-                            aopts.account_id.as_str(),
-                            label,
-                            expiry_odt,
-                            *read_write,
-                        )
-                        .await
-                    {
+                    let result = if permissions.is_empty() {
+                        client
+                            .idm_service_account_generate_api_token(
+                                aopts.account_id.as_str(),
+                                label,
+                                expiry_odt,
+                                *read_write,
+                            )
+                            .await
+                    } else {
+                        client
+                            .idm_service_account_generate_scoped_api_token(
+                                aopts.account_id.as_str(),
+                                label,
+                                expiry_odt,
+                                permissions,
+                            )
+                            .await
+                    };
+
+                    match result {
                         Ok(new_token) => match copt.output_mode {
                             OutputMode::Json => {
                                 let message = AccountChangeMessage {
@@ -187,6 +223,67 @@ impl ServiceAccountOpt {
                     }
                 }
             }, // End ServiceAccountOpt::ApiToken
+            ServiceAccountOpt::JwtKey { commands } => match commands {
+                ServiceAccountJwtKey::List(apo) => {
+                    let client = apo.copt.to_client(OpType::Read).await;
+                    match client
+                        .idm_service_account_list_jwt_public_key(apo.aopts.account_id.as_str())
+                        .await
+                    {
+                        Ok(labels) => {
+                            if labels.is_empty() {
+                                println!("No jwt keys exist");
+                            } else {
+                                for label in labels {
+                                    println!("{}", label);
+                                }
+                            }
+                        }
+                        Err(e) => handle_client_error(e, apo.copt.output_mode),
+                    }
+                }
+                ServiceAccountJwtKey::Add {
+                    aopts,
+                    copt,
+                    label,
+                    public_key_path,
+                } => {
+                    let public_key_pem = match std::fs::read_to_string(public_key_path) {
+                        Ok(pem) => pem,
+                        Err(err) => {
+                            error!(
+                                ?err,
+                                "Unable to read public key from {}",
+                                public_key_path.display()
+                            );
+                            return;
+                        }
+                    };
+
+                    let client = copt.to_client(OpType::Write).await;
+                    match client
+                        .idm_service_account_add_jwt_public_key(
+                            aopts.account_id.as_str(),
+                            label,
+                            &public_key_pem,
+                        )
+                        .await
+                    {
+                        Ok(()) => println!("Success"),
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
+                ServiceAccountJwtKey::Remove { aopts, copt, label } => {
+                    let client = copt.to_client(OpType::Write).await;
+                    match client
+                        .idm_service_account_delete_jwt_public_key(aopts.account_id.as_str(), label)
+                        .await
+                    {
+                        Ok(()) => println!("Success"),
+                        Err(e) => handle_client_error(e, copt.output_mode),
+                    }
+                }
+            }, // End ServiceAccountOpt::JwtKey
             ServiceAccountOpt::Posix { commands } => match commands {
                 ServiceAccountPosix::Show(aopt) => {
                     let client = aopt.copt.to_client(OpType::Read).await;
@@ -209,6 +306,17 @@ impl ServiceAccountOpt {
                         .await
                     {
                         handle_client_error(e, aopt.copt.output_mode)
+                    } else if let Some(home_quota) = aopt.home_quota {
+                        if let Err(e) = client
+                            .idm_service_account_set_attr(
+                                aopt.aopts.account_id.as_str(),
+                                ATTR_POSIX_HOME_QUOTA,
+                                &[home_quota.to_string().as_str()],
+                            )
+                            .await
+                        {
+                            handle_client_error(e, aopt.copt.output_mode)
+                        }
                     }
                 }
                 ServiceAccountPosix::ResetGidnumber { copt, account_id } => {
@@ -242,6 +350,26 @@ impl ServiceAccountOpt {
                         }
                     }
                 }
+                AccountUserAuthToken::Oauth2Status(apo) => {
+                    let client = apo.copt.to_client(OpType::Read).await;
+                    match client
+                        .idm_account_list_oauth2_sessions(apo.aopts.account_id.as_str())
+                        .await
+                    {
+                        Ok(sessions) => {
+                            if sessions.is_empty() {
+                                println!("No sessions exist");
+                            } else {
+                                for session in sessions {
+                                    println!("session: {}", session);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            error!("Error listing sessions -> {:?}", e);
+                        }
+                    }
+                }
                 AccountUserAuthToken::Destroy {
                     aopts,
                     copt,
@@ -301,6 +429,26 @@ impl ServiceAccountOpt {
                         handle_client_error(e, aopt.copt.output_mode)
                     }
                 }
+                AccountSsh::SetExpiry(aopt) => {
+                    let client = aopt.copt.to_client(OpType::Write).await;
+                    let expiry = match try_expire_at_from_string(aopt.datetime.as_str()) {
+                        Ok(expiry) => {
+                            expiry.and_then(|expiry| OffsetDateTime::parse(&expiry, &Rfc3339).ok())
+                        }
+                        Err(()) => return,
+                    };
+                    match client
+                        .idm_service_account_set_ssh_pubkey_expiry(
+                            aopt.aopts.account_id.as_str(),
+                            aopt.tag.as_str(),
+                            expiry,
+                        )
+                        .await
+                    {
+                        Err(e) => handle_client_error(e, aopt.copt.output_mode),
+                        _ => println!("Success"),
+                    }
+                }
             }, // end ServiceAccountOpt::Ssh
             ServiceAccountOpt::List(copt) => {
                 let client = copt.to_client(OpType::Read).await;
@@ -1,32 +1,40 @@
 use crate::common::{OpType, ToClientError};
 use std::cmp::Reverse;
 use std::collections::BTreeMap;
-use std::fs::{create_dir, File};
+use std::fs::{create_dir, read, write, File};
 use std::io::{self, BufReader, BufWriter, ErrorKind, IsTerminal, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use compact_jwt::{
-    traits::JwsVerifiable, Jwk, JwsCompact, JwsEs256Verifier, JwsVerifier, JwtError,
+    jws::JwsBuilder, traits::JwsVerifiable, Jwk, JwsCompact, JwsEs256Signer, JwsEs256Verifier,
+    JwsSigner, JwsVerifier, JwtError,
 };
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::Select;
 use kanidm_client::{ClientError, KanidmClient};
 use kanidm_proto::constants::CLIENT_TOKEN_CACHE;
 use kanidm_proto::internal::UserAuthToken;
-use kanidm_proto::v1::{AuthAllowed, AuthResponse, AuthState};
+use kanidm_proto::v1::{AuthAllowed, AuthMech, AuthResponse, AuthState};
 #[cfg(target_family = "unix")]
 use libc::umask;
+use openssl::pkey::PKey;
+use url::Url;
 use webauthn_authenticator_rs::prelude::RequestChallengeResponse;
 
 use crate::common::prompt_for_username_get_username;
 use crate::webauthn::get_authenticator;
-use crate::{CommonOpt, LoginOpt, LogoutOpt, ReauthOpt, SessionOpt};
+use crate::{BreakGlassOpt, CommonOpt, LoginOpt, LogoutOpt, ReauthOpt, SessionOpt};
 
 use serde::{Deserialize, Serialize};
 
 static TOKEN_DIR: &str = "~/.cache";
 
+/// How long a jwt assertion is valid for. It is used immediately, so this only needs to allow
+/// for clock skew.
+const JWT_ASSERTION_LIFETIME: u64 = 60;
+
 #[derive(Debug, Serialize, Clone, Deserialize, Default)]
 pub struct TokenInstance {
     keys: BTreeMap<String, Jwk>,
@@ -282,6 +290,31 @@ async fn do_backup_code(client: &mut KanidmClient) -> Result<AuthResponse, Clien
     client.auth_step_backup_code(backup_code.trim()).await
 }
 
+async fn do_email_link(client: &mut KanidmClient) -> Result<AuthResponse, ClientError> {
+    println!("A link to login has been sent to the mail address of your account.");
+    print!("Enter the link: ");
+    // We flush stdout so it'll write the buffer to screen, continuing operation. Without it, the application halts.
+    #[allow(clippy::unwrap_used)]
+    io::stdout().flush().unwrap();
+    let mut link = String::new();
+    loop {
+        if let Err(e) = io::stdin().read_line(&mut link) {
+            error!("Failed to read from stdin -> {:?}", e);
+            return Err(ClientError::SystemError);
+        };
+        if !link.trim().is_empty() {
+            break;
+        };
+    }
+    // Accept either the whole link, or only the token from it.
+    let link = link.trim();
+    let token = link
+        .split_once("token=")
+        .map(|(_, token)| token)
+        .unwrap_or(link);
+    client.auth_step_email_link(token).await
+}
+
 async fn do_totp(client: &mut KanidmClient) -> Result<AuthResponse, ClientError> {
     let totp = loop {
         print!("Enter TOTP: ");
@@ -341,6 +374,80 @@ async fn do_securitykey(
     client.auth_step_securitykey_complete(auth).await
 }
 
+#[derive(Serialize)]
+struct JwtAssertionClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    exp: u64,
+    iat: u64,
+    nonce: String,
+}
+
+/// Sign a jwt assertion for the service account with the private key at this path. The
+/// assertion contains the nonce the server issued, so it can only be used for this login.
+fn sign_jwt_assertion(
+    key_path: &Path,
+    account: &str,
+    audience: &Url,
+    nonce: &str,
+) -> Result<String, String> {
+    let key_pem = read(key_path)
+        .map_err(|err| format!("Unable to read {} - {:?}", key_path.display(), err))?;
+
+    let key_der = PKey::private_key_from_pem(&key_pem)
+        .and_then(|key| key.ec_key())
+        .and_then(|ec_key| ec_key.private_key_to_der())
+        .map_err(|err| format!("Unable to load ECDSA private key - {:?}", err))?;
+
+    let signer = JwsEs256Signer::from_es256_der(&key_der)
+        .map_err(|err| format!("Unable to load ECDSA private key - {:?}", err))?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| format!("Invalid system time - {:?}", err))?
+        .as_secs();
+
+    let claims = JwtAssertionClaims {
+        iss: account.to_string(),
+        sub: account.to_string(),
+        aud: audience.to_string(),
+        exp: now + JWT_ASSERTION_LIFETIME,
+        iat: now,
+        nonce: nonce.to_string(),
+    };
+
+    let jws = JwsBuilder::into_json(&claims)
+        .map(|builder| builder.build())
+        .map_err(|err| format!("Unable to serialise jwt assertion - {:?}", err))?;
+
+    signer
+        .sign(&jws)
+        .map(|jwsc| jwsc.to_string())
+        .map_err(|err| format!("Unable to sign jwt assertion - {:?}", err))
+}
+
+async fn do_jwt_assertion(client: &KanidmClient, account: &str, key_path: &Path) {
+    let nonce = client
+        .auth_jwt_assertion_begin(account)
+        .await
+        .unwrap_or_else(|e| {
+            error!("Error during authentication begin phase: {:?}", e);
+            std::process::exit(1);
+        });
+
+    let assertion = sign_jwt_assertion(key_path, account, client.get_origin(), &nonce)
+        .unwrap_or_else(|err| {
+            error!("{}", err);
+            std::process::exit(1);
+        });
+
+    if let Err(e) = client.auth_jwt_assertion_complete(&assertion).await {
+        error!("Error in authentication phase: {:?}", e);
+        std::process::exit(1);
+    }
+}
+
 async fn process_auth_state(
     mut allowed: Vec<AuthAllowed>,
     mut client: KanidmClient,
@@ -383,6 +490,15 @@ async fn process_auth_state(
             AuthAllowed::Anonymous => client.auth_step_anonymous().await,
             AuthAllowed::Password => do_password(&mut client, maybe_password).await,
             AuthAllowed::BackupCode => do_backup_code(&mut client).await,
+            AuthAllowed::EmailLink => do_email_link(&mut client).await,
+            AuthAllowed::Federated(_) => {
+                error!("Logging in with an upstream identity provider requires a web browser");
+                std::process::exit(1);
+            }
+            AuthAllowed::JwtAssertion(_) => {
+                error!("Logging in with a jwt assertion requires --jwt-key");
+                std::process::exit(1);
+            }
             AuthAllowed::Totp => do_totp(&mut client).await,
             AuthAllowed::Passkey(chal) => do_passkey(&mut client, chal.clone()).await,
             AuthAllowed::SecurityKey(chal) => do_securitykey(&mut client, chal.clone()).await,
@@ -404,6 +520,10 @@ async fn process_auth_state(
                 error!("Authentication Denied: {:?}", reason);
                 std::process::exit(1);
             }
+            AuthState::CredentialUpdateRequired(_) => {
+                error!("Your password has expired. Log in with a web browser to change it.");
+                std::process::exit(1);
+            }
             _ => {
                 error!("Error in authentication phase: invalid authstate");
                 std::process::exit(1);
@@ -412,6 +532,11 @@ async fn process_auth_state(
         // Loop again.
     }
 
+    persist_session_token(&client, instance_name).await;
+}
+
+/// Verify the session token the client was issued, and add it to the token store.
+async fn persist_session_token(client: &KanidmClient, instance_name: &Option<String>) {
     // Read the current tokens. If we can't read them, IGNORE!!!
     let mut tokens = read_tokens(&client.get_token_cache_path()).unwrap_or_default();
 
@@ -495,6 +620,20 @@ async fn process_auth_state(
 
     // Success!
     println!("Login Success for {}", spn);
+
+    print_announcements(client).await;
+}
+
+/// Show the announcements that the administrators want command line users to see. These are
+/// informational, so a failure to fetch them does not affect the login.
+async fn print_announcements(client: &KanidmClient) {
+    match client.idm_announcement_active().await {
+        Ok(announcements) => announcements
+            .iter()
+            .filter(|announcement| announcement.cli)
+            .for_each(|announcement| println!("{}", announcement)),
+        Err(err) => debug!(?err, "Unable to retrieve announcements"),
+    }
 }
 
 impl LoginOpt {
@@ -512,6 +651,14 @@ impl LoginOpt {
             }
         };
 
+        let instance_name = &self.copt.instance;
+
+        if let Some(jwt_key) = &self.jwt_key {
+            do_jwt_assertion(&client, username, jwt_key).await;
+            persist_session_token(&client, instance_name).await;
+            return;
+        }
+
         // What auth mechanisms exist?
         let mut mechs: Vec<_> = client
             .auth_step_init(username)
@@ -521,6 +668,9 @@ impl LoginOpt {
                 std::process::exit(1);
             })
             .into_iter()
+            // Upstream identity providers can only be used from a web browser, and jwt
+            // assertions need a key that was given on the command line.
+            .filter(|mech| *mech != AuthMech::Federated && *mech != AuthMech::JwtAssertion)
             .collect();
 
         mechs.sort_unstable_by(|a, b| Reverse(a).cmp(&Reverse(b)));
@@ -560,8 +710,6 @@ impl LoginOpt {
                 std::process::exit(1);
             });
 
-        let instance_name = &self.copt.instance;
-
         // We now have the first auth state, so we can proceed until complete.
         process_auth_state(allowed, client, &self.password, instance_name).await;
     }
@@ -590,6 +738,82 @@ impl ReauthOpt {
     }
 }
 
+impl BreakGlassOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            BreakGlassOpt::Challenge { copt, .. }
+            | BreakGlassOpt::Login { copt, .. }
+            | BreakGlassOpt::Code { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            BreakGlassOpt::Challenge { copt, out } => {
+                let client = copt.to_unauth_client();
+                let challenge = client
+                    .auth_break_glass_challenge()
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!("Error requesting break glass challenge: {:?}", e);
+                        std::process::exit(1);
+                    });
+
+                if let Err(err) = write(out, &challenge.challenge) {
+                    error!(?err, "Unable to write challenge to {}", out.display());
+                    std::process::exit(1);
+                }
+
+                println!("Challenge written to {}", out.display());
+                println!("Sign it with the break glass key within 5 minutes, then run:");
+                println!(
+                    "kanidm break-glass login --id {} --signature <signature file>",
+                    challenge.id
+                );
+            }
+            BreakGlassOpt::Login {
+                copt,
+                id,
+                signature,
+            } => {
+                let signature = read(signature).unwrap_or_else(|err| {
+                    error!(
+                        ?err,
+                        "Unable to read signature from {}",
+                        signature.display()
+                    );
+                    std::process::exit(1);
+                });
+
+                let client = copt.to_unauth_client();
+                if let Err(e) = client.auth_break_glass(*id, signature).await {
+                    error!("Break glass authentication denied: {:?}", e);
+                    std::process::exit(1);
+                }
+
+                persist_session_token(&client, &copt.instance).await;
+            }
+            BreakGlassOpt::Code { copt, account } => {
+                let code = dialoguer::Password::new()
+                    .with_prompt("Enter recovery code")
+                    .interact()
+                    .unwrap_or_else(|e| {
+                        error!("Failed to create recovery code prompt -- {:?}", e);
+                        std::process::exit(1);
+                    });
+
+                let client = copt.to_unauth_client();
+                if let Err(e) = client.auth_break_glass_code(account, code.as_str()).await {
+                    error!("Break glass authentication denied: {:?}", e);
+                    std::process::exit(1);
+                }
+
+                persist_session_token(&client, &copt.instance).await;
+            }
+        }
+    }
+}
+
 impl LogoutOpt {
     pub fn debug(&self) -> bool {
         self.copt.debug
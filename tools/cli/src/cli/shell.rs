@@ -0,0 +1,454 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::io::{self, IsTerminal};
+
+use clap::{CommandFactory, Parser};
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{BasicHistory, Completion, Input};
+use kanidm_client::KanidmClient;
+use kanidm_proto::v1::Entry;
+
+use crate::common::OpType;
+use crate::{handle_client_error, KanidmClientOpt, KanidmClientParser, ShellOpt};
+
+const SHELL_HISTORY_MAX_ENTRIES: usize = 256;
+
+impl ShellOpt {
+    pub fn debug(&self) -> bool {
+        self.copt.debug
+    }
+
+    pub async fn exec(&self) {
+        // Authenticate once up front. Commands run in the shell then select the same session
+        // from the token cache rather than prompting for an account each time.
+        let client = self.copt.to_client(OpType::Read).await;
+
+        let spn = match client.whoami().await {
+            Ok(Some(entry)) => entry
+                .attrs
+                .get("spn")
+                .and_then(|v| v.first())
+                .cloned()
+                .unwrap_or_default(),
+            Ok(None) => {
+                error!("Authentication with cached token failed, can't start the shell.");
+                return;
+            }
+            Err(e) => {
+                handle_client_error(e, self.copt.output_mode);
+                return;
+            }
+        };
+
+        self.set_session_env(&spn);
+
+        if io::stdin().is_terminal() {
+            let completion = ShellCompletion::new(&client).await;
+            interactive(&spn, &completion).await;
+        } else {
+            batch().await;
+        }
+    }
+
+    /// Commands parsed in the shell read their connection options from the environment when
+    /// they aren't given, so that they use the same instance and session as the shell.
+    fn set_session_env(&self, spn: &str) {
+        env::set_var("KANIDM_NAME", spn);
+
+        if let Some(instance) = &self.copt.instance {
+            env::set_var("KANIDM_INSTANCE", instance);
+        }
+        if let Some(addr) = &self.copt.addr {
+            env::set_var("KANIDM_URL", addr);
+        }
+        if let Some(ca_path) = &self.copt.ca_path {
+            env::set_var("KANIDM_CA_PATH", ca_path);
+        }
+        if let Some(token_cache_path) = &self.copt.token_cache_path {
+            env::set_var("KANIDM_TOKEN_CACHE_PATH", token_cache_path);
+        }
+        if self.copt.debug {
+            env::set_var("KANIDM_DEBUG", "true");
+        }
+    }
+}
+
+async fn interactive(spn: &str, completion: &ShellCompletion) {
+    println!(
+        "Type 'help' to list the available commands, and 'exit' or Ctrl-D to leave the shell."
+    );
+
+    let theme = ColorfulTheme::default();
+    let mut history = BasicHistory::new()
+        .max_entries(SHELL_HISTORY_MAX_ENTRIES)
+        .no_duplicates(true);
+
+    loop {
+        let line = match Input::<String>::with_theme(&theme)
+            .with_prompt(format!("kanidm ({})", spn))
+            .allow_empty(true)
+            .completion_with(completion)
+            .history_with(&mut history)
+            .interact_text()
+        {
+            Ok(line) => line,
+            // Ctrl-D or the terminal went away.
+            Err(_) => break,
+        };
+
+        if !run_line(&line).await {
+            break;
+        }
+    }
+}
+
+async fn batch() {
+    loop {
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {
+                if !run_line(&line).await {
+                    break;
+                }
+            }
+            Err(err) => {
+                error!(?err, "Unable to read command from stdin");
+                break;
+            }
+        }
+    }
+}
+
+/// Run a single line of the shell, returning false if the shell should exit.
+async fn run_line(line: &str) -> bool {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+        return true;
+    }
+
+    if line == "exit" || line == "quit" {
+        return false;
+    }
+
+    let Some(words) = split_words(line) else {
+        error!("Unterminated quote in command");
+        return true;
+    };
+
+    let args = std::iter::once("kanidm".to_string()).chain(words);
+
+    match KanidmClientParser::try_parse_from(args) {
+        Ok(KanidmClientParser {
+            commands: KanidmClientOpt::Shell(_),
+        }) => {
+            error!("Already running in the shell");
+        }
+        Ok(KanidmClientParser {
+            commands: KanidmClientOpt::Version {},
+        }) => {
+            println!("kanidm {}", env!("KANIDM_PKG_VERSION"));
+        }
+        Ok(opt) => Box::pin(opt.commands.exec()).await,
+        // This is also how help is displayed.
+        Err(err) => {
+            let _ = err.print();
+        }
+    }
+
+    true
+}
+
+/// Split a line into words in the same way as a posix shell, so that values with spaces can
+/// be quoted. Returns none if a quote is unterminated.
+fn split_words(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None => match c {
+                '\'' | '"' => {
+                    quote = Some(c);
+                    in_word = true;
+                }
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        word.push(escaped);
+                    }
+                    in_word = true;
+                }
+                c if c.is_whitespace() => {
+                    if in_word {
+                        words.push(std::mem::take(&mut word));
+                        in_word = false;
+                    }
+                }
+                c => {
+                    word.push(c);
+                    in_word = true;
+                }
+            },
+        }
+    }
+
+    if quote.is_some() {
+        return None;
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    Some(words)
+}
+
+/// Completes subcommands and flags from the cli definition, and the names of entries and
+/// attributes from the server.
+struct ShellCompletion {
+    command: clap::Command,
+    names: BTreeSet<String>,
+}
+
+impl ShellCompletion {
+    async fn new(client: &KanidmClient) -> Self {
+        let mut names = BTreeSet::new();
+
+        // Lists that the account isn't able to read are skipped, they just won't be completed.
+        let entry_lists = [
+            client.idm_person_account_list().await,
+            client.idm_service_account_list().await,
+            client.idm_group_list().await,
+            client.idm_oauth2_rs_list().await,
+        ];
+
+        for entries in entry_lists.into_iter().flatten() {
+            names.extend(attr_values(&entries, "name"));
+        }
+
+        if let Ok(entries) = client.idm_schema_attributetype_list().await {
+            names.extend(attr_values(&entries, "attributename"));
+        }
+
+        debug!("Loaded {} names for completion", names.len());
+
+        ShellCompletion {
+            command: KanidmClientParser::command(),
+            names,
+        }
+    }
+
+    fn candidates(&self, words: &[String], partial: &str) -> Vec<String> {
+        let mut command = &self.command;
+        for word in words {
+            match command.find_subcommand(word) {
+                Some(subcommand) => command = subcommand,
+                None => break,
+            }
+        }
+
+        if partial.starts_with('-') {
+            command
+                .get_arguments()
+                .filter(|arg| !arg.is_hide_set())
+                .filter_map(|arg| arg.get_long())
+                .map(|long| format!("--{}", long))
+                .collect()
+        } else if command.has_subcommands() {
+            command
+                .get_subcommands()
+                .filter(|subcommand| !subcommand.is_hide_set())
+                .map(|subcommand| subcommand.get_name().to_string())
+                .collect()
+        } else {
+            self.names.iter().cloned().collect()
+        }
+    }
+}
+
+impl Completion for ShellCompletion {
+    fn get(&self, input: &str) -> Option<String> {
+        let partial = input.rsplit(char::is_whitespace).next().unwrap_or_default();
+        let prefix = &input[..input.len() - partial.len()];
+        let words = split_words(prefix)?;
+
+        let matches: Vec<String> = self
+            .candidates(&words, partial)
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(partial))
+            .collect();
+
+        let completed = match matches.as_slice() {
+            [] => return None,
+            [only] => format!("{} ", only),
+            [first, rest @ ..] => rest.iter().fold(first.clone(), |common, candidate| {
+                common
+                    .chars()
+                    .zip(candidate.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect()
+            }),
+        };
+
+        if completed == partial {
+            return None;
+        }
+
+        Some(format!("{}{}", prefix, completed))
+    }
+}
+
+fn attr_values(entries: &[Entry], attr: &str) -> Vec<String> {
+    entries
+        .iter()
+        .filter_map(|entry| entry.attrs.get(attr))
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_words, ShellCompletion};
+    use crate::KanidmClientParser;
+    use clap::CommandFactory;
+    use dialoguer::Completion;
+    use std::collections::BTreeSet;
+
+    fn words(words: &[&str]) -> Option<Vec<String>> {
+        Some(words.iter().map(|word| word.to_string()).collect())
+    }
+
+    fn completion(names: &[&str]) -> ShellCompletion {
+        ShellCompletion {
+            command: KanidmClientParser::command(),
+            names: names.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words(""), words(&[]));
+        assert_eq!(split_words("   "), words(&[]));
+        assert_eq!(
+            split_words("person get  alice"),
+            words(&["person", "get", "alice"])
+        );
+        assert_eq!(split_words("  group list\t"), words(&["group", "list"]));
+
+        // Quotes and escapes keep spaces within a word.
+        assert_eq!(
+            split_words("person update alice --displayname 'Alice Smith'"),
+            words(&["person", "update", "alice", "--displayname", "Alice Smith"])
+        );
+        assert_eq!(
+            split_words(r#"group create "new group""#),
+            words(&["group", "create", "new group"])
+        );
+        assert_eq!(split_words(r"a\ b c"), words(&["a b", "c"]));
+
+        // The other quote, and escapes, are literal within quotes.
+        assert_eq!(split_words(r#""it's""#), words(&["it's"]));
+        assert_eq!(split_words(r#"'say "hi"'"#), words(&[r#"say "hi""#]));
+        assert_eq!(split_words(r"'a\b'"), words(&[r"a\b"]));
+
+        // Adjacent quoted and unquoted parts form one word, and empty quotes are a word.
+        assert_eq!(split_words(r#"a"b c"'d'"#), words(&["ab cd"]));
+        assert_eq!(split_words("a '' b"), words(&["a", "", "b"]));
+
+        // A trailing escape is dropped.
+        assert_eq!(split_words(r"a\"), words(&["a"]));
+    }
+
+    #[test]
+    fn test_split_words_unterminated() {
+        assert_eq!(split_words("'"), None);
+        assert_eq!(split_words(r#"person get "alice"#), None);
+        assert_eq!(split_words(r#"group create 'new group""#), None);
+    }
+
+    #[test]
+    fn test_completion_candidates() {
+        let completion = completion(&["alice", "idm_admins"]);
+
+        // Subcommands are completed from the cli definition, without those that are hidden.
+        let candidates: BTreeSet<String> = completion.candidates(&[], "").into_iter().collect();
+        assert!(candidates.contains("person"));
+        assert!(candidates.contains("group"));
+        assert!(candidates.contains("shell"));
+        assert!(!candidates.contains("raw"));
+
+        let candidates: BTreeSet<String> = completion
+            .candidates(&["person".to_string()], "")
+            .into_iter()
+            .collect();
+        assert!(candidates.contains("get"));
+        assert!(candidates.contains("list"));
+        assert!(!candidates.contains("person"));
+        assert!(!candidates.contains("certificate"));
+
+        // Once there are no further subcommands, the names from the server are completed.
+        let candidates = completion.candidates(&["person".to_string(), "get".to_string()], "");
+        assert_eq!(candidates, vec!["alice", "idm_admins"]);
+
+        // Flags are completed for the deepest subcommand, without those that are hidden.
+        let candidates: BTreeSet<String> = completion
+            .candidates(&["login".to_string()], "-")
+            .into_iter()
+            .collect();
+        assert!(candidates.contains("--name"));
+        assert!(candidates.contains("--jwt-key"));
+        assert!(!candidates.contains("--password"));
+
+        // Words that aren't subcommands, such as values, don't change the completion.
+        let candidates: BTreeSet<String> = completion
+            .candidates(&["logout".to_string(), "alice".to_string()], "--")
+            .into_iter()
+            .collect();
+        assert!(candidates.contains("--local-only"));
+    }
+
+    #[test]
+    fn test_completion_get() {
+        let completion = completion(&["alice", "alicia", "bob"]);
+
+        // A single match is completed, with a space ready for the next word.
+        assert_eq!(completion.get("pers"), Some("person ".to_string()));
+        assert_eq!(
+            completion.get("person get b"),
+            Some("person get bob ".to_string())
+        );
+        assert_eq!(
+            completion.get("logout --local"),
+            Some("logout --local-only ".to_string())
+        );
+
+        // Many matches are completed to their common prefix.
+        assert_eq!(
+            completion.get("person get a"),
+            Some("person get alic".to_string())
+        );
+        assert_eq!(completion.get("person cr"), Some("person cre".to_string()));
+
+        // There is nothing to complete if the common prefix is already present, or there
+        // are no matches.
+        assert_eq!(completion.get("person get alic"), None);
+        assert_eq!(completion.get("person ge"), None);
+        assert_eq!(completion.get("person get z"), None);
+        assert_eq!(completion.get("person cer"), None);
+
+        // Quoted words are understood, and an unterminated quote is not completed.
+        assert_eq!(
+            completion.get("'person' get b"),
+            Some("'person' get bob ".to_string())
+        );
+        assert_eq!(completion.get("person get \"a b"), None);
+    }
+}
@@ -14,6 +14,8 @@ impl SynchOpt {
             | SynchOpt::Finalise { copt, .. }
             | SynchOpt::Terminate { copt, .. }
             | SynchOpt::SetYieldAttributes { copt, .. }
+            | SynchOpt::ListConflicts { copt, .. }
+            | SynchOpt::ClearConflicts { copt, .. }
             | SynchOpt::SetCredentialPortal { copt, .. } => copt.debug,
         }
     }
@@ -99,6 +101,21 @@ impl SynchOpt {
                     Err(e) => handle_client_error(e, copt.output_mode),
                 }
             }
+            SynchOpt::ListConflicts { account_id, copt } => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_sync_account_get_conflicts(account_id).await {
+                    Ok(conflicts) if conflicts.is_empty() => println!("No conflicts"),
+                    Ok(conflicts) => conflicts.iter().for_each(|c| println!("{}", c)),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            SynchOpt::ClearConflicts { account_id, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_sync_account_clear_conflicts(account_id).await {
+                    Ok(()) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
             SynchOpt::ForceRefresh { account_id, copt } => {
                 let client = copt.to_client(OpType::Write).await;
                 match client.idm_sync_account_force_refresh(account_id).await {
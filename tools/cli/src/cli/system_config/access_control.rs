@@ -0,0 +1,54 @@
+use crate::common::OpType;
+use crate::{handle_client_error, AccessControlOpt, OutputMode};
+
+impl AccessControlOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            AccessControlOpt::Preview { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            AccessControlOpt::Preview { copt, sample } => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.system_access_control_preview(*sample).await {
+                    Ok(preview) => match copt.output_mode {
+                        OutputMode::Json => {
+                            println!(
+                                "{}",
+                                serde_json::to_string(&preview).expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => {
+                            if preview.staged.is_empty() {
+                                println!("No access control changes are staged");
+                                return;
+                            }
+                            println!("staged: {}", preview.staged.join(", "));
+                            println!(
+                                "sampled {} accounts and {} entries",
+                                preview.accounts_sampled, preview.entries_sampled
+                            );
+                            if preview.changes.is_empty() {
+                                println!("No changes in access were found in the sample");
+                            }
+                            for change in preview.changes.iter() {
+                                println!("---");
+                                println!("account: {}", change.account);
+                                println!("target: {}", change.target);
+                                for right in change.gained.iter() {
+                                    println!("  + {}", right);
+                                }
+                                for right in change.lost.iter() {
+                                    println!("  - {}", right);
+                                }
+                            }
+                        }
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+        }
+    }
+}
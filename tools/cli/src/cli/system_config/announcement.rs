@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+use kanidm_proto::internal::AnnouncementSeverity;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::common::OpType;
+use crate::{handle_client_error, AnnouncementOpt, OutputMode};
+
+fn parse_time(time: Option<&str>) -> Result<Option<OffsetDateTime>, ()> {
+    time.map(|time| {
+        OffsetDateTime::parse(time, &Rfc3339).map_err(|e| {
+            error!("Invalid time {} -> {:?}", time, e);
+        })
+    })
+    .transpose()
+}
+
+impl AnnouncementOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            AnnouncementOpt::List(copt) => copt.debug,
+            AnnouncementOpt::Get(nopt) | AnnouncementOpt::Delete(nopt) => nopt.copt.debug,
+            AnnouncementOpt::Create { copt, .. } | AnnouncementOpt::SetMessage { copt, .. } => {
+                copt.debug
+            }
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            AnnouncementOpt::List(copt) => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_announcement_list().await {
+                    Ok(r) => match copt.output_mode {
+                        OutputMode::Json => {
+                            let r_attrs: Vec<_> = r.iter().map(|entry| &entry.attrs).collect();
+                            println!(
+                                "{}",
+                                serde_json::to_string(&r_attrs).expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => r.iter().for_each(|ent| println!("{}", ent)),
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            AnnouncementOpt::Get(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_announcement_get(nopt.name.as_str()).await {
+                    Ok(Some(e)) => println!("{}", e),
+                    Ok(None) => println!("No matching entries"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            AnnouncementOpt::Create {
+                name,
+                message,
+                severity,
+                start,
+                end,
+                cli,
+                copt,
+            } => {
+                let Ok(severity) = AnnouncementSeverity::from_str(severity) else {
+                    error!(
+                        "Invalid severity {} - must be info, warning or critical",
+                        severity
+                    );
+                    return;
+                };
+                let Ok(start) = parse_time(start.as_deref()) else {
+                    return;
+                };
+                let Ok(end) = parse_time(end.as_deref()) else {
+                    return;
+                };
+
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_announcement_create(name, message, severity, start, end, *cli)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            AnnouncementOpt::SetMessage {
+                name,
+                message,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_announcement_set_message(name, message).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            AnnouncementOpt::Delete(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client.idm_announcement_delete(nopt.name.as_str()).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+        }
+    }
+}
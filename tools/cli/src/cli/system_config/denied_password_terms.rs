@@ -0,0 +1,47 @@
+use crate::common::OpType;
+
+use crate::{handle_client_error, DeniedPasswordTermsOpt};
+
+impl DeniedPasswordTermsOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            DeniedPasswordTermsOpt::Show { copt }
+            | DeniedPasswordTermsOpt::Append { copt, .. }
+            | DeniedPasswordTermsOpt::Remove { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            DeniedPasswordTermsOpt::Show { copt } => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.system_denied_password_terms_get().await {
+                    Ok(list) => {
+                        for i in list {
+                            println!("{}", i);
+                        }
+                        eprintln!("--");
+                        eprintln!("Success");
+                    }
+                    Err(e) => crate::handle_client_error(e, copt.output_mode),
+                }
+            }
+            DeniedPasswordTermsOpt::Append { copt, terms } => {
+                let client = copt.to_client(OpType::Write).await;
+
+                match client.system_denied_password_terms_append(terms).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            DeniedPasswordTermsOpt::Remove { copt, terms } => {
+                let client = copt.to_client(OpType::Write).await;
+
+                match client.system_denied_password_terms_remove(terms).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+        }
+    }
+}
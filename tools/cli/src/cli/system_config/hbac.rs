@@ -0,0 +1,157 @@
+use crate::common::OpType;
+use crate::{handle_client_error, HbacOpt, OutputMode};
+
+impl HbacOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            HbacOpt::List(copt) => copt.debug,
+            HbacOpt::Get(nopt) | HbacOpt::Delete(nopt) => nopt.copt.debug,
+            HbacOpt::Create { copt, .. }
+            | HbacOpt::AddMember { copt, .. }
+            | HbacOpt::RemoveMember { copt, .. }
+            | HbacOpt::AddHost { copt, .. }
+            | HbacOpt::RemoveHost { copt, .. }
+            | HbacOpt::AddHostGroup { copt, .. }
+            | HbacOpt::RemoveHostGroup { copt, .. }
+            | HbacOpt::AddService { copt, .. }
+            | HbacOpt::RemoveService { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            HbacOpt::List(copt) => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_hbac_rule_list().await {
+                    Ok(r) => match copt.output_mode {
+                        OutputMode::Json => {
+                            let r_attrs: Vec<_> = r.iter().map(|entry| &entry.attrs).collect();
+                            println!(
+                                "{}",
+                                serde_json::to_string(&r_attrs).expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => r.iter().for_each(|ent| println!("{}", ent)),
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HbacOpt::Get(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_hbac_rule_get(nopt.name.as_str()).await {
+                    Ok(Some(e)) => println!("{}", e),
+                    Ok(None) => println!("No matching entries"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            HbacOpt::Create {
+                name,
+                description,
+                deny,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_hbac_rule_create(name, description.as_deref(), *deny)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HbacOpt::Delete(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client.idm_hbac_rule_delete(nopt.name.as_str()).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            HbacOpt::AddMember {
+                name,
+                members,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_hbac_rule_add_members(name, members).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HbacOpt::RemoveMember {
+                name,
+                members,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_hbac_rule_remove_members(name, members).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HbacOpt::AddHost { name, hosts, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_hbac_rule_add_hosts(name, hosts).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HbacOpt::RemoveHost { name, hosts, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_hbac_rule_remove_hosts(name, hosts).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HbacOpt::AddHostGroup {
+                name,
+                host_groups,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_hbac_rule_add_host_groups(name, host_groups)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HbacOpt::RemoveHostGroup {
+                name,
+                host_groups,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_hbac_rule_remove_host_groups(name, host_groups)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HbacOpt::AddService {
+                name,
+                services,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_hbac_rule_add_services(name, services).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HbacOpt::RemoveService {
+                name,
+                services,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_hbac_rule_remove_services(name, services).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+        }
+    }
+}
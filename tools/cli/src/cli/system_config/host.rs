@@ -0,0 +1,168 @@
+use crate::common::OpType;
+use crate::{handle_client_error, HostOpt, OutputMode};
+
+impl HostOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            HostOpt::List(copt) => copt.debug,
+            HostOpt::Get(nopt) | HostOpt::Delete(nopt) | HostOpt::ResetKioskCacheTimeout(nopt) => {
+                nopt.copt.debug
+            }
+            HostOpt::Create { copt, .. }
+            | HostOpt::GenerateJoinToken { copt, .. }
+            | HostOpt::SetKioskMode { copt, .. }
+            | HostOpt::SetKioskCacheTimeout { copt, .. }
+            | HostOpt::AddKioskGuestGroup { copt, .. }
+            | HostOpt::RemoveKioskGuestGroup { copt, .. }
+            | HostOpt::AddKerberosServicePrincipal { copt, .. }
+            | HostOpt::RemoveKerberosServicePrincipal { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            HostOpt::List(copt) => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_host_list().await {
+                    Ok(r) => match copt.output_mode {
+                        OutputMode::Json => {
+                            let r_attrs: Vec<_> = r.iter().map(|entry| &entry.attrs).collect();
+                            println!(
+                                "{}",
+                                serde_json::to_string(&r_attrs).expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => r.iter().for_each(|ent| println!("{}", ent)),
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostOpt::Get(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_host_get(nopt.name.as_str()).await {
+                    Ok(Some(e)) => println!("{}", e),
+                    Ok(None) => println!("No matching entries"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            HostOpt::Create {
+                name,
+                displayname,
+                description,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_host_create(
+                        name,
+                        displayname.as_deref().unwrap_or(name),
+                        description.as_deref(),
+                    )
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostOpt::Delete(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client.idm_host_delete(nopt.name.as_str()).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            HostOpt::GenerateJoinToken { name, ttl, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_host_generate_join_token(name, *ttl).await {
+                    Ok(join_token) => match copt.output_mode {
+                        OutputMode::Json => println!(
+                            "{}",
+                            serde_json::to_string(&join_token).expect("Failed to serialise json")
+                        ),
+                        OutputMode::Text => {
+                            println!("Enroll the machine as this host with:");
+                            println!("kanidm-unix enroll {} {}", name, join_token);
+                        }
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostOpt::SetKioskMode { name, enable, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_host_set_kiosk_mode(name, *enable).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostOpt::SetKioskCacheTimeout {
+                name,
+                timeout,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_host_set_kiosk_cache_timeout(name, *timeout)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostOpt::ResetKioskCacheTimeout(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_host_reset_kiosk_cache_timeout(nopt.name.as_str())
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            HostOpt::AddKioskGuestGroup { name, groups, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_host_add_kiosk_guest_groups(name, groups).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostOpt::RemoveKioskGuestGroup { name, groups, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_host_remove_kiosk_guest_groups(name, groups)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostOpt::AddKerberosServicePrincipal {
+                name,
+                principals,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_host_add_kerberos_service_principals(name, principals)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostOpt::RemoveKerberosServicePrincipal {
+                name,
+                principals,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_host_remove_kerberos_service_principals(name, principals)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::common::OpType;
+use crate::{handle_client_error, HostGroupOpt, OutputMode};
+
+impl HostGroupOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            HostGroupOpt::List(copt) => copt.debug,
+            HostGroupOpt::Get(nopt) | HostGroupOpt::Delete(nopt) => nopt.copt.debug,
+            HostGroupOpt::Create { copt, .. }
+            | HostGroupOpt::AddMember { copt, .. }
+            | HostGroupOpt::RemoveMember { copt, .. }
+            | HostGroupOpt::AddTag { copt, .. }
+            | HostGroupOpt::RemoveTag { copt, .. }
+            | HostGroupOpt::TagMachine { copt, .. }
+            | HostGroupOpt::UntagMachine { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            HostGroupOpt::List(copt) => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_host_group_list().await {
+                    Ok(r) => match copt.output_mode {
+                        OutputMode::Json => {
+                            let r_attrs: Vec<_> = r.iter().map(|entry| &entry.attrs).collect();
+                            println!(
+                                "{}",
+                                serde_json::to_string(&r_attrs).expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => r.iter().for_each(|ent| println!("{}", ent)),
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostGroupOpt::Get(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_host_group_get(nopt.name.as_str()).await {
+                    Ok(Some(e)) => println!("{}", e),
+                    Ok(None) => println!("No matching entries"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            HostGroupOpt::Create {
+                name,
+                description,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_host_group_create(name, description.as_deref())
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostGroupOpt::Delete(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client.idm_host_group_delete(nopt.name.as_str()).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            HostGroupOpt::AddMember {
+                name,
+                members,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_host_group_add_members(name, members).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostGroupOpt::RemoveMember {
+                name,
+                members,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_host_group_remove_members(name, members).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostGroupOpt::AddTag { name, tags, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_host_group_add_tags(name, tags).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostGroupOpt::RemoveTag { name, tags, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_host_group_remove_tags(name, tags).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostGroupOpt::TagMachine {
+                account_id,
+                tags,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_machine_add_host_tags(account_id, tags).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            HostGroupOpt::UntagMachine {
+                account_id,
+                tags,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_machine_remove_host_tags(account_id, tags).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+        }
+    }
+}
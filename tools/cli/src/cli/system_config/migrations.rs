@@ -0,0 +1,70 @@
+use crate::common::OpType;
+use crate::{handle_client_error, MigrationsOpt, OutputMode};
+use kanidm_proto::constants::{
+    ATTR_MIGRATION_APPLIED_AT, ATTR_MIGRATION_DURATION, ATTR_MIGRATION_ENTRIES_CHANGED,
+    ATTR_MIGRATION_FROM_LEVEL, ATTR_MIGRATION_NAME, ATTR_MIGRATION_TO_LEVEL,
+};
+use kanidm_proto::v1::Entry;
+
+impl MigrationsOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            MigrationsOpt::List(copt) => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            MigrationsOpt::List(copt) => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.system_migrations_list().await {
+                    Ok(mut records) => {
+                        // The times are in RFC3339 format, so they sort in the order they were applied.
+                        records.sort_by(|a, b| {
+                            first_value(a, ATTR_MIGRATION_APPLIED_AT)
+                                .cmp(first_value(b, ATTR_MIGRATION_APPLIED_AT))
+                        });
+
+                        match copt.output_mode {
+                            OutputMode::Json => {
+                                let r_attrs: Vec<_> =
+                                    records.iter().map(|entry| &entry.attrs).collect();
+                                println!(
+                                    "{}",
+                                    serde_json::to_string(&r_attrs)
+                                        .expect("Failed to serialise json")
+                                );
+                            }
+                            OutputMode::Text => {
+                                if records.is_empty() {
+                                    println!("No migrations have been recorded");
+                                }
+                                for record in records.iter() {
+                                    println!(
+                                        "{} {} (level {} -> {}) took {}ms and changed {} entries",
+                                        first_value(record, ATTR_MIGRATION_APPLIED_AT),
+                                        first_value(record, ATTR_MIGRATION_NAME),
+                                        first_value(record, ATTR_MIGRATION_FROM_LEVEL),
+                                        first_value(record, ATTR_MIGRATION_TO_LEVEL),
+                                        first_value(record, ATTR_MIGRATION_DURATION),
+                                        first_value(record, ATTR_MIGRATION_ENTRIES_CHANGED),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+        }
+    }
+}
+
+fn first_value<'a>(entry: &'a Entry, attr: &str) -> &'a str {
+    entry
+        .attrs
+        .get(attr)
+        .and_then(|values| values.first())
+        .map(String::as_str)
+        .unwrap_or_default()
+}
@@ -1,3 +1,13 @@
+pub mod access_control;
+pub mod announcement;
 pub mod api;
 pub mod badlist;
 pub mod denied_names;
+pub mod denied_password_terms;
+pub mod hbac;
+pub mod host;
+pub mod hostgroup;
+pub mod migrations;
+pub mod oidc_upstream;
+pub mod saml2;
+pub mod webhook;
@@ -0,0 +1,96 @@
+use crate::common::OpType;
+use crate::{handle_client_error, OidcUpstreamOpt, OutputMode};
+
+fn prompt_client_secret() -> Option<String> {
+    dialoguer::Password::new()
+        .with_prompt("Enter the client secret")
+        .interact()
+        .map_err(|e| {
+            error!("Failed to create password prompt -- {:?}", e);
+        })
+        .ok()
+}
+
+impl OidcUpstreamOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            OidcUpstreamOpt::List(copt) => copt.debug,
+            OidcUpstreamOpt::Get(nopt)
+            | OidcUpstreamOpt::SetClientSecret(nopt)
+            | OidcUpstreamOpt::Delete(nopt) => nopt.copt.debug,
+            OidcUpstreamOpt::Create { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            OidcUpstreamOpt::List(copt) => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_oidc_upstream_list().await {
+                    Ok(r) => match copt.output_mode {
+                        OutputMode::Json => {
+                            let r_attrs: Vec<_> = r.iter().map(|entry| &entry.attrs).collect();
+                            println!(
+                                "{}",
+                                serde_json::to_string(&r_attrs).expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => r.iter().for_each(|ent| println!("{}", ent)),
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            OidcUpstreamOpt::Get(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_oidc_upstream_get(nopt.name.as_str()).await {
+                    Ok(Some(e)) => println!("{}", e),
+                    Ok(None) => println!("No matching entries"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            OidcUpstreamOpt::Create {
+                name,
+                displayname,
+                issuer,
+                client_id,
+                client_secret,
+                copt,
+            } => {
+                let Some(client_secret) = client_secret.clone().or_else(prompt_client_secret)
+                else {
+                    return;
+                };
+
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oidc_upstream_create(name, displayname, issuer, client_id, &client_secret)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            OidcUpstreamOpt::SetClientSecret(nopt) => {
+                let Some(client_secret) = prompt_client_secret() else {
+                    return;
+                };
+
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client
+                    .idm_oidc_upstream_set_client_secret(nopt.name.as_str(), &client_secret)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            OidcUpstreamOpt::Delete(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client.idm_oidc_upstream_delete(nopt.name.as_str()).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::common::OpType;
+use crate::{handle_client_error, OutputMode, Saml2Opt};
+
+impl Saml2Opt {
+    pub fn debug(&self) -> bool {
+        match self {
+            Saml2Opt::List(copt) => copt.debug,
+            Saml2Opt::Get(nopt) | Saml2Opt::Metadata(nopt) | Saml2Opt::Delete(nopt) => {
+                nopt.copt.debug
+            }
+            Saml2Opt::Create { copt, .. }
+            | Saml2Opt::SetAcsUrls { copt, .. }
+            | Saml2Opt::UpdateGroupMap { copt, .. }
+            | Saml2Opt::DeleteGroupMap { copt, .. }
+            | Saml2Opt::RevokeKey { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            Saml2Opt::List(copt) => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_saml2_list().await {
+                    Ok(r) => match copt.output_mode {
+                        OutputMode::Json => {
+                            let r_attrs: Vec<_> = r.iter().map(|entry| &entry.attrs).collect();
+                            println!(
+                                "{}",
+                                serde_json::to_string(&r_attrs).expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => r.iter().for_each(|ent| println!("{}", ent)),
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            Saml2Opt::Get(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_saml2_get(nopt.name.as_str()).await {
+                    Ok(Some(e)) => println!("{}", e),
+                    Ok(None) => println!("No matching entries"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Saml2Opt::Create {
+                name,
+                displayname,
+                entity_id,
+                acs_url,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_saml2_create(name, displayname, entity_id, acs_url)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            Saml2Opt::SetAcsUrls {
+                name,
+                acs_urls,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_saml2_set_acs_urls(name, acs_urls).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            Saml2Opt::UpdateGroupMap {
+                name,
+                group,
+                values,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_saml2_update_group_map(
+                        name,
+                        group,
+                        values.iter().map(String::as_str).collect(),
+                    )
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            Saml2Opt::DeleteGroupMap { name, group, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_saml2_delete_group_map(name, group).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            Saml2Opt::Metadata(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_saml2_metadata(nopt.name.as_str()).await {
+                    Ok(metadata) => println!("{}", metadata),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            Saml2Opt::RevokeKey { name, key_id, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_saml2_revoke_key(name, key_id).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            Saml2Opt::Delete(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client.idm_saml2_delete(nopt.name.as_str()).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::common::OpType;
+use crate::{handle_client_error, OutputMode, WebhookOpt};
+
+impl WebhookOpt {
+    pub fn debug(&self) -> bool {
+        match self {
+            WebhookOpt::List(copt) => copt.debug,
+            WebhookOpt::Get(nopt)
+            | WebhookOpt::Delete(nopt)
+            | WebhookOpt::ShowSecret(nopt)
+            | WebhookOpt::RotateSecret(nopt)
+            | WebhookOpt::Deliveries(nopt) => nopt.copt.debug,
+            WebhookOpt::Create { copt, .. }
+            | WebhookOpt::SetUrl { copt, .. }
+            | WebhookOpt::AddFilter { copt, .. }
+            | WebhookOpt::RemoveFilter { copt, .. } => copt.debug,
+        }
+    }
+
+    pub async fn exec(&self) {
+        match self {
+            WebhookOpt::List(copt) => {
+                let client = copt.to_client(OpType::Read).await;
+                match client.idm_webhook_list().await {
+                    Ok(r) => match copt.output_mode {
+                        OutputMode::Json => {
+                            let r_attrs: Vec<_> = r.iter().map(|entry| &entry.attrs).collect();
+                            println!(
+                                "{}",
+                                serde_json::to_string(&r_attrs).expect("Failed to serialise json")
+                            );
+                        }
+                        OutputMode::Text => r.iter().for_each(|ent| println!("{}", ent)),
+                    },
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            WebhookOpt::Get(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_webhook_get(nopt.name.as_str()).await {
+                    Ok(Some(e)) => println!("{}", e),
+                    Ok(None) => println!("No matching entries"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            WebhookOpt::Create {
+                name,
+                url,
+                description,
+                filters,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client
+                    .idm_webhook_create(name, url, description.as_deref(), filters)
+                    .await
+                {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            WebhookOpt::Delete(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client.idm_webhook_delete(nopt.name.as_str()).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            WebhookOpt::SetUrl { name, url, copt } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_webhook_set_url(name, url).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            WebhookOpt::AddFilter {
+                name,
+                filters,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_webhook_add_filters(name, filters).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            WebhookOpt::RemoveFilter {
+                name,
+                filters,
+                copt,
+            } => {
+                let client = copt.to_client(OpType::Write).await;
+                match client.idm_webhook_remove_filters(name, filters).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, copt.output_mode),
+                }
+            }
+            WebhookOpt::ShowSecret(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_webhook_get_secret(nopt.name.as_str()).await {
+                    Ok(Some(secret)) => match nopt.copt.output_mode {
+                        OutputMode::Text => println!("{}", secret),
+                        OutputMode::Json => println!("{{\"secret\": \"{}\"}}", secret),
+                    },
+                    Ok(None) => eprintln!("No secret configured"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            WebhookOpt::RotateSecret(nopt) => {
+                let client = nopt.copt.to_client(OpType::Write).await;
+                match client.idm_webhook_rotate_secret(nopt.name.as_str()).await {
+                    Ok(_) => println!("Success"),
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+            WebhookOpt::Deliveries(nopt) => {
+                let client = nopt.copt.to_client(OpType::Read).await;
+                match client.idm_webhook_list_deliveries(nopt.name.as_str()).await {
+                    Ok(deliveries) => match nopt.copt.output_mode {
+                        OutputMode::Json => println!(
+                            "{}",
+                            serde_json::to_string(&deliveries).expect("Failed to serialise json")
+                        ),
+                        OutputMode::Text => {
+                            for delivery in deliveries {
+                                println!("---");
+                                println!("id: {}", delivery.id);
+                                println!("time: {}", delivery.time);
+                                println!("event: {} {}", delivery.action, delivery.target);
+                                println!("state: {}", delivery.state);
+                                println!("attempts: {}", delivery.attempts);
+                                if let Some(last_error) = delivery.last_error {
+                                    println!("last error: {}", last_error);
+                                }
+                            }
+                        }
+                    },
+                    Err(e) => handle_client_error(e, nopt.copt.output_mode),
+                }
+            }
+        }
+    }
+}
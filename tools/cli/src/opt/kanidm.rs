@@ -1,5 +1,8 @@
 use clap::{builder::PossibleValue, Args, Subcommand, ValueEnum};
-use kanidm_proto::internal::ImageType;
+use kanidm_proto::internal::{
+    ImageType, Oauth2GrantType, Oauth2JwtSignAlg, Oauth2RedirectUriMode, Oauth2ResponseType,
+    UiTheme,
+};
 use std::fmt;
 
 #[derive(Debug, Args)]
@@ -109,6 +112,19 @@ pub struct GroupNamedMembers {
     copt: CommonOpt,
 }
 
+#[derive(Debug, Args)]
+pub struct GroupAddMembers {
+    name: String,
+    #[clap(required = true, num_args(1..))]
+    members: Vec<String>,
+    /// The membership lapses after this duration, such as `8h`, `30m` or `1d12h`. This
+    /// is useful to temporarily grant privileges.
+    #[clap(long = "expire-in")]
+    expire_in: Option<String>,
+    #[clap(flatten)]
+    copt: CommonOpt,
+}
+
 #[derive(Debug, Args)]
 pub struct GroupPosixOpt {
     name: String,
@@ -186,6 +202,15 @@ pub enum GroupAccountPolicyOpt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    /// Set the time in seconds that a session may be unused before it expires. Using the
+    /// session refreshes it, up to the session expiry. The minimum is 300 seconds.
+    #[clap(name = "auth-idle-timeout")]
+    AuthSessionIdleTimeout {
+        name: String,
+        timeout: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
     /// Set the minimum credential class that members may authenticate with. Valid values
     /// in order of weakest to strongest are: "any" "mfa" "passkey" "attested_passkey".
     #[clap(name = "credential-type-minimum")]
@@ -196,6 +221,51 @@ pub enum GroupAccountPolicyOpt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    /// Set the number of authentication failures after which a credential of a member of
+    /// this group is locked. Setting this to 0 disables lockout.
+    #[clap(name = "auth-lockout-threshold")]
+    AuthLockoutThreshold {
+        name: String,
+        threshold: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the time in seconds over which authentication failures are counted towards
+    /// the lockout threshold.
+    #[clap(name = "auth-lockout-window")]
+    AuthLockoutWindow {
+        name: String,
+        window: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the time in seconds that a credential remains locked once the lockout
+    /// threshold is reached.
+    #[clap(name = "auth-lockout-duration")]
+    AuthLockoutDuration {
+        name: String,
+        duration: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the time in seconds after their creation that members of this group expire.
+    /// This only applies to members that don't already have an expiry.
+    #[clap(name = "account-lifetime")]
+    AccountLifetime {
+        name: String,
+        lifetime: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the time in seconds after they expire that members of this group are moved to
+    /// the recycle bin.
+    #[clap(name = "account-archive-delay")]
+    AccountArchiveDelay {
+        name: String,
+        delay: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
     /// Set the minimum character length of passwords for accounts.
     #[clap(name = "password-minimum-length")]
     PasswordMinimumLength {
@@ -205,6 +275,25 @@ pub enum GroupAccountPolicyOpt {
         copt: CommonOpt,
     },
 
+    /// Set the number of previous passwords that members of this group may not reuse.
+    #[clap(name = "password-history-count")]
+    PasswordHistoryCount {
+        name: String,
+        count: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+
+    /// Set the maximum age of passwords of members of this group in seconds. A member who
+    /// authenticates with an older password must change it before they are issued a session.
+    #[clap(name = "password-maximum-age")]
+    PasswordMaximumAge {
+        name: String,
+        age: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+
     /// Set the maximum time for privilege session expiry in seconds.
     #[clap(name = "privilege-expiry")]
     PrivilegedSessionExpiry {
@@ -255,6 +344,132 @@ pub enum GroupAccountPolicyOpt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    /// Sets whether members of this group may set a posix password. If denied, an
+    /// existing posix password can no longer be used to authenticate.
+    #[clap(name = "allow-unix-password")]
+    AllowUnixPassword {
+        name: String,
+        #[clap(name = "allow", action = clap::ArgAction::Set)]
+        allow: bool,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Sets whether members of this group may issue api tokens to themselves from their
+    /// profile. Existing tokens are not revoked if this is denied.
+    #[clap(name = "allow-api-tokens")]
+    AllowApiTokens {
+        name: String,
+        #[clap(name = "allow", action = clap::ArgAction::Set)]
+        allow: bool,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Sets whether members of this group may authenticate with a link that is sent to their
+    /// primary mail address. This requires smtp to be configured on the server.
+    #[clap(name = "allow-email-link")]
+    AllowEmailLink {
+        name: String,
+        #[clap(name = "allow", action = clap::ArgAction::Set)]
+        allow: bool,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Sets whether new passwords of members of this group are rejected if they are found in
+    /// the dataset of breached passwords. This requires a dataset to be loaded by the server.
+    #[clap(name = "password-breach-check")]
+    PasswordBreachCheck {
+        name: String,
+        #[clap(name = "enable", action = clap::ArgAction::Set)]
+        enable: bool,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the host tags of the machines that members of this group may log in to. If any
+    /// policy of an account sets login host tags, it may only log in to hosts with one of them.
+    #[clap(name = "login-host-tag")]
+    LoginHostTag {
+        name: String,
+        #[clap(required = true, num_args(1..))]
+        tags: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the ssh public key types that members of this group may add to their account,
+    /// such as "ssh-ed25519" or "sk-ssh-ed25519@openssh.com". Existing keys are not removed.
+    #[clap(name = "ssh-key-allowed-type")]
+    SshKeyAllowedType {
+        name: String,
+        #[clap(required = true, num_args(1..))]
+        key_types: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the minimum length in bits of rsa ssh public keys that members of this group may
+    /// add to their account.
+    #[clap(name = "ssh-key-rsa-minimum-bits")]
+    SshKeyRsaMinimumBits {
+        name: String,
+        bits: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the login shell of members of this group that do not have their own shell set.
+    #[clap(name = "posix-default-shell")]
+    PosixDefaultShell {
+        name: String,
+        shell: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the template of the home directory name of members of this group. The template may
+    /// contain {name}, {spn}, {uuid}, {displayname} and {gidnumber}.
+    #[clap(name = "posix-home-template")]
+    PosixHomeTemplate {
+        name: String,
+        template: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the format of the gecos of members of this group. The format may contain {name},
+    /// {spn}, {uuid}, {displayname} and {gidnumber}.
+    #[clap(name = "posix-gecos-format")]
+    PosixGecosFormat {
+        name: String,
+        format: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the name of the skeleton that is copied into the home directory of members of this
+    /// group when it is first created. Skeletons are found in the "home_skeleton_prefix" of unixd.
+    #[clap(name = "posix-home-skeleton")]
+    PosixHomeSkeleton {
+        name: String,
+        skeleton: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the networks, in CIDR notation, that members of this group are trusted to
+    /// authenticate from. Authentication from any other network requires the credential
+    /// type set by "credential-type-minimum-untrusted-network", or is denied if it is not set.
+    #[clap(name = "trusted-network")]
+    TrustedNetwork {
+        name: String,
+        #[clap(required = true, num_args(1..))]
+        networks: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the minimum credential class that members may authenticate with from outside
+    /// of a trusted network. Valid values in order of weakest to strongest are: "any" "mfa"
+    /// "passkey" "attested_passkey".
+    #[clap(name = "credential-type-minimum-untrusted-network")]
+    CredentialTypeMinimumUntrustedNetwork {
+        name: String,
+        #[clap(value_enum)]
+        value: AccountPolicyCredentialType,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
 
     /// Reset the maximum time for session expiry to its default value
     #[clap(name = "reset-auth-expiry")]
@@ -263,6 +478,13 @@ pub enum GroupAccountPolicyOpt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    /// Remove the idle timeout, so that sessions only expire at their session expiry.
+    #[clap(name = "reset-auth-idle-timeout")]
+    ResetAuthSessionIdleTimeout {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
     /// Reset the minimum character length of passwords to its default value.
     #[clap(name = "reset-password-minimum-length")]
     ResetPasswordMinimumLength {
@@ -270,6 +492,20 @@ pub enum GroupAccountPolicyOpt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    /// Remove the password history requirement, so that previous passwords are not remembered.
+    #[clap(name = "reset-password-history-count")]
+    ResetPasswordHistoryCount {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the maximum age of passwords, so that they never expire.
+    #[clap(name = "reset-password-maximum-age")]
+    ResetPasswordMaximumAge {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
     /// Reset the maximum time for privilege session expiry to its default value.
     #[clap(name = "reset-privilege-expiry")]
     ResetPrivilegedSessionExpiry {
@@ -299,6 +535,105 @@ pub enum GroupAccountPolicyOpt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    /// Remove the login host tags, so that members may log in to any host.
+    #[clap(name = "reset-login-host-tag")]
+    ResetLoginHostTag {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the ssh public key type restriction, so that any type may be added.
+    #[clap(name = "reset-ssh-key-allowed-type")]
+    ResetSshKeyAllowedType {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the minimum length of rsa ssh public keys.
+    #[clap(name = "reset-ssh-key-rsa-minimum-bits")]
+    ResetSshKeyRsaMinimumBits {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the default login shell.
+    #[clap(name = "reset-posix-default-shell")]
+    ResetPosixDefaultShell {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the home directory template.
+    #[clap(name = "reset-posix-home-template")]
+    ResetPosixHomeTemplate {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the gecos format.
+    #[clap(name = "reset-posix-gecos-format")]
+    ResetPosixGecosFormat {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the home directory skeleton.
+    #[clap(name = "reset-posix-home-skeleton")]
+    ResetPosixHomeSkeleton {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the trusted networks, so that authentication is not restricted by network.
+    #[clap(name = "reset-trusted-network")]
+    ResetTrustedNetwork {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Reset the minimum credential class required from outside of a trusted network.
+    #[clap(name = "reset-credential-type-minimum-untrusted-network")]
+    ResetCredentialTypeMinimumUntrustedNetwork {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the lockout threshold, so that credentials are not locked after repeated
+    /// authentication failures.
+    #[clap(name = "reset-auth-lockout-threshold")]
+    ResetAuthLockoutThreshold {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Reset the lockout failure window to its default value.
+    #[clap(name = "reset-auth-lockout-window")]
+    ResetAuthLockoutWindow {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Reset the lockout duration to its default value.
+    #[clap(name = "reset-auth-lockout-duration")]
+    ResetAuthLockoutDuration {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the account lifetime, so that members are not given an expiry.
+    #[clap(name = "reset-account-lifetime")]
+    ResetAccountLifetime {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove the archive delay, so that expired members are not moved to the recycle bin.
+    #[clap(name = "reset-account-archive-delay")]
+    ResetAccountArchiveDelay {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -358,6 +693,20 @@ pub enum GroupOpt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    /// Nest this group beneath a parent group. The group and its members inherit the
+    /// memberships of the parent and every group above it.
+    #[clap(name = "set-parent")]
+    SetParent {
+        /// The name of the group
+        name: String,
+        /// The name/spn of the group to nest this group beneath.
+        parent: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Remove this group from beneath its parent group.
+    #[clap(name = "reset-parent")]
+    ResetParent(Named),
     /// Rename an existing group
     #[clap(name = "rename")]
     Rename {
@@ -373,7 +722,7 @@ pub enum GroupOpt {
     PurgeMembers(Named),
     /// Add new members to a group
     #[clap(name = "add-members")]
-    AddMembers(GroupNamedMembers),
+    AddMembers(GroupAddMembers),
     /// Remove the named members from this group
     #[clap(name = "remove-members")]
     RemoveMembers(GroupNamedMembers),
@@ -389,6 +738,61 @@ pub enum GroupOpt {
         #[clap(subcommand)]
         commands: GroupAccountPolicyOpt,
     },
+    /// Request to join groups, and approve or deny the requests of others.
+    #[clap(name = "membership-request")]
+    MembershipRequest {
+        #[clap(subcommand)]
+        commands: GroupMembershipRequestOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GroupMembershipRequestOpt {
+    /// Allow accounts to request to join this group. Requests are approved by the entry
+    /// managers of the group.
+    #[clap(name = "enable")]
+    Enable {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Stop accounts from requesting to join this group.
+    #[clap(name = "disable")]
+    Disable {
+        name: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Request to join a group.
+    #[clap(name = "create")]
+    Create {
+        name: String,
+        /// Why you need to be a member of the group, shown to the approvers.
+        #[clap(long)]
+        justification: Option<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// List your requests to join groups, and the requests awaiting your approval.
+    #[clap(name = "list")]
+    List(CommonOpt),
+    /// Approve a request to join a group.
+    #[clap(name = "approve")]
+    Approve {
+        id: Uuid,
+        /// The membership lapses after this duration, such as `8h`, `30m` or `1d12h`.
+        #[clap(long = "expire-in")]
+        expire_in: Option<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Deny a request to join a group.
+    #[clap(name = "deny")]
+    Deny {
+        id: Uuid,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
 }
 
 #[derive(Clone, Debug, ValueEnum)]
@@ -467,6 +871,22 @@ pub struct AccountNamedTagOpt {
     tag: String,
 }
 
+#[derive(Debug, Args)]
+pub struct AccountNamedTagExpireDateTimeOpt {
+    #[clap(flatten)]
+    aopts: AccountCommonOpt,
+    #[clap(flatten)]
+    copt: CommonOpt,
+    #[clap(name = "tag")]
+    tag: String,
+    #[clap(name = "datetime", verbatim_doc_comment)]
+    /// This accepts multiple options:
+    /// - An RFC3339 time of the format "YYYY-MM-DDTHH:MM:SS+TZ", "2020-09-25T11:22:02+10:00"
+    /// - One of "any", "clear" or "never" to remove the expiry.
+    /// - "now" to expire immediately
+    datetime: String,
+}
+
 #[derive(Debug, Args)]
 pub struct AccountNamedTagPkOpt {
     #[clap(flatten)]
@@ -503,6 +923,13 @@ pub enum AccountCredential {
     /// Show the status of this accounts credentials.
     #[clap(name = "status")]
     Status(AccountNamedOpt),
+    /// Show the authentication failures recorded against this accounts credentials, and
+    /// whether they are currently locked.
+    #[clap(name = "softlock-status")]
+    SoftlockStatus(AccountNamedOpt),
+    /// Clear the authentication failures and any lockout of this accounts credentials.
+    #[clap(name = "softlock-clear")]
+    SoftlockClear(AccountNamedOpt),
     /// Interactively update/change the credentials for an account
     #[clap(name = "update")]
     Update(AccountNamedOpt),
@@ -520,6 +947,39 @@ pub enum AccountCredential {
         /// Optionally set how many seconds the reset token should be valid for.
         /// Default: 3600 seconds
         ttl: Option<u32>,
+        /// Optionally set how many times the reset token may be used to update
+        /// credentials before it is consumed. Default: 1
+        #[clap(long = "max-uses")]
+        max_uses: Option<u32>,
+        /// Mail the reset token to the primary mail address of the person, rather than
+        /// displaying it.
+        #[clap(long = "send-mail")]
+        send_mail: bool,
+    },
+    /// Import a password hash from another system, such as bcrypt, crypt(3), argon2id or an
+    /// NT hash. The hash replaces the accounts password, and is upgraded the next time the
+    /// person authenticates. If the hash is not given, it is read from standard input.
+    #[clap(name = "import-hash")]
+    ImportHash {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+        #[clap(flatten)]
+        copt: CommonOpt,
+        /// The password hash to import.
+        hash: Option<String>,
+    },
+    /// List the reset tokens of this account that have not yet expired.
+    #[clap(name = "list-reset-tokens")]
+    ListResetTokens(AccountNamedOpt),
+    /// Revoke a reset token of this account so that it can no longer be used.
+    #[clap(name = "revoke-reset-token")]
+    RevokeResetToken {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+        #[clap(flatten)]
+        copt: CommonOpt,
+        /// The reference of the token, as shown by list-reset-tokens.
+        token_ref: String,
     },
 }
 
@@ -535,6 +995,22 @@ pub enum AccountRadius {
     #[clap(name = "delete-secret")]
     /// Remove the configured RADIUS secret for the user.
     DeleteSecret(AccountNamedOpt),
+    /// Issue a client certificate for EAP-TLS from a PEM encoded certificate signing request.
+    /// The certificate is written to standard output.
+    #[clap(name = "issue-certificate")]
+    IssueCertificate {
+        account_id: String,
+        csr_path: PathBuf,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Show the certificate authority that issues client certificates for EAP-TLS. This
+    /// must be trusted by the RADIUS server.
+    #[clap(name = "show-ca-certificate")]
+    ShowCaCertificate {
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
 }
 
 #[derive(Debug, Args)]
@@ -546,6 +1022,9 @@ pub struct AccountPosixOpt {
     #[clap(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
     /// Set the user's login shell
     shell: Option<String>,
+    #[clap(long)]
+    /// Set the filesystem quota of the user's home directory in MiB
+    home_quota: Option<u32>,
     #[clap(flatten)]
     copt: CommonOpt,
 }
@@ -613,6 +1092,9 @@ pub enum AccountSsh {
     Add(AccountNamedTagPkOpt),
     #[clap(name = "delete-publickey")]
     Delete(AccountNamedTagOpt),
+    /// Set when a public key expires, after which it is no longer distributed to clients
+    #[clap(name = "set-publickey-expiry")]
+    SetExpiry(AccountNamedTagExpireDateTimeOpt),
 }
 
 #[derive(Debug, Subcommand)]
@@ -628,17 +1110,84 @@ pub enum AccountValidity {
     BeginFrom(AccountNamedValidDateTimeOpt),
 }
 
+#[derive(Debug, Args)]
+pub struct ContractorCreateOpt {
+    #[clap(flatten)]
+    aopts: AccountCommonOpt,
+    #[clap(name = "display-name")]
+    display_name: String,
+    /// The person who is accountable for this contractor. They are notified before the
+    /// contractor's account expires.
+    #[clap(name = "sponsor")]
+    sponsor: String,
+    /// When the contractor's account expires, as an RFC3339 time of the format
+    /// "YYYY-MM-DDTHH:MM:SS+TZ", "2020-09-25T11:22:02+10:00"
+    #[clap(name = "datetime")]
+    datetime: String,
+    #[clap(flatten)]
+    copt: CommonOpt,
+}
+
 #[derive(Debug, Subcommand)]
-pub enum AccountCertificate {
-    #[clap(name = "status")]
-    Status {
-        account_id: String,
+pub enum PersonContractor {
+    /// Create a new contractor's account, with a sponsor and an expiry time. The expiry can be
+    /// extended later with `person validity expire-at`.
+    #[clap(name = "create")]
+    Create(ContractorCreateOpt),
+    /// Show the sponsor of a contractor
+    #[clap(name = "get-sponsor")]
+    GetSponsor(AccountNamedOpt),
+    /// Change the sponsor of a contractor
+    #[clap(name = "set-sponsor")]
+    SetSponsor {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+        #[clap(name = "sponsor")]
+        sponsor: String,
         #[clap(flatten)]
         copt: CommonOpt,
     },
-    #[clap(name = "create")]
-    Create {
-        account_id: String,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PersonNotification {
+    /// Show the categories of security notification this person has opted out of
+    #[clap(name = "show")]
+    Show(AccountNamedOpt),
+    /// Stop mailing this person a category of security notification, such as
+    /// "new_device_session". Categories that the server requires are still sent.
+    #[clap(name = "opt-out")]
+    OptOut {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+        #[clap(name = "category")]
+        category: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Resume mailing this person a category of security notification
+    #[clap(name = "opt-in")]
+    OptIn {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+        #[clap(name = "category")]
+        category: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AccountCertificate {
+    #[clap(name = "status")]
+    Status {
+        account_id: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "create")]
+    Create {
+        account_id: String,
         certificate_path: PathBuf,
         #[clap(flatten)]
         copt: CommonOpt,
@@ -650,6 +1199,10 @@ pub enum AccountUserAuthToken {
     /// Show the status of logged in sessions associated to this account.
     #[clap(name = "status")]
     Status(AccountNamedOpt),
+    /// Show the status of OAuth2 sessions issued to this account, including the reason
+    /// a session was revoked by the server.
+    #[clap(name = "oauth2-status")]
+    Oauth2Status(AccountNamedOpt),
     /// Destroy / revoke a session for this account. Access to the
     /// session (user auth token) is NOT required, only the uuid of the session.
     #[clap(name = "destroy")]
@@ -724,6 +1277,35 @@ pub enum PersonOpt {
         #[clap(subcommand)]
         commands: AccountValidity,
     },
+    /// Manage contractors, who are people outside of the organisation with a sponsor and a
+    /// limited time account
+    #[clap(name = "contractor")]
+    Contractor {
+        #[clap(subcommand)]
+        commands: PersonContractor,
+    },
+    /// Manage which security notifications are mailed to this person
+    #[clap(name = "notification")]
+    Notification {
+        #[clap(subcommand)]
+        commands: PersonNotification,
+    },
+    /// Show the account policy that applies to this person, merged from the policies of
+    /// their groups
+    #[clap(name = "account-policy")]
+    AccountPolicy(AccountNamedOpt),
+    /// Issue a short lived, read only session that acts as this person. This requires
+    /// membership of idm_impersonation_admins, and is always audited.
+    #[clap(name = "impersonate")]
+    Impersonate {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+        #[clap(flatten)]
+        copt: CommonOpt,
+        /// How long the session lasts in seconds, up to 3600. Defaults to 900.
+        #[clap(long = "expiry")]
+        expiry: Option<u64>,
+    },
     #[clap(name = "certificate", hide = true)]
     Certificate {
         #[clap(subcommand)]
@@ -765,6 +1347,11 @@ pub enum ServiceAccountApiToken {
         expiry: Option<String>,
         #[clap(long = "rw")]
         read_write: bool,
+        /// Restrict the token to only modify these attributes of entries of a class, and
+        /// optionally with a name prefix. The form is `class[:name_prefix]=attr[,attr]...`,
+        /// such as `group:app-=member`. This may be repeated, and requires `--rw`.
+        #[clap(long = "permission", requires = "read_write")]
+        permissions: Vec<String>,
     },
     /// Destroy / revoke an api token from this service account. Access to the
     /// token is NOT required, only the tag/uuid of the token.
@@ -780,6 +1367,40 @@ pub enum ServiceAccountApiToken {
     },
 }
 
+#[derive(Debug, Subcommand)]
+pub enum ServiceAccountJwtKey {
+    /// List the labels of the public keys that may sign jwt assertions for this service account.
+    #[clap(name = "list")]
+    List(AccountNamedOpt),
+    /// Register a public key that may sign jwt assertions to authenticate this service account.
+    /// The service account can then login with `kanidm login --jwt-key <private key>`.
+    #[clap(name = "add")]
+    Add {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+        #[clap(flatten)]
+        copt: CommonOpt,
+        /// A label to identify this key.
+        #[clap(name = "label")]
+        label: String,
+        /// The path to an ECDSA P-256 public key in PEM format.
+        #[clap(name = "public-key-path")]
+        public_key_path: PathBuf,
+    },
+    /// Remove a public key from this service account. Assertions signed by the key will no
+    /// longer be accepted.
+    #[clap(name = "remove")]
+    Remove {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+        #[clap(flatten)]
+        copt: CommonOpt,
+        /// The label of the key to remove.
+        #[clap(name = "label")]
+        label: String,
+    },
+}
+
 #[derive(Debug, Args)]
 pub struct ServiceAccountUpdateOpt {
     #[clap(flatten)]
@@ -825,6 +1446,12 @@ pub enum ServiceAccountOpt {
         #[clap(subcommand)]
         commands: ServiceAccountApiToken,
     },
+    /// Manage the public keys that may sign jwt assertions to authenticate this service account.
+    #[clap(name = "jwt-key")]
+    JwtKey {
+        #[clap(subcommand)]
+        commands: ServiceAccountJwtKey,
+    },
     /// Manage posix extensions for this service account allowing access to unix/linux systems
     #[clap(name = "posix")]
     Posix {
@@ -888,11 +1515,28 @@ pub enum RecycleOpt {
     #[clap(name = "get")]
     /// Display an object from the recycle bin
     Get(Named),
+    #[clap(name = "inspect")]
+    /// Show the attributes of a recycled object and when each was last changed, so that the
+    /// object can be checked before it is revived. Requires system administrator rights.
+    Inspect(RecycleInspectOpt),
     #[clap(name = "revive")]
     /// Revive a recycled object into a live (accessible) state - this is the opposite of "delete"
     Revive(Named),
 }
 
+#[derive(Debug, Args)]
+pub struct RecycleInspectOpt {
+    /// The uuid or name of the recycled object
+    pub name: String,
+    /// Highlight the attributes that have changed since this time, in RFC3339 format such as
+    /// 2024-01-01T00:00:00Z. Attributes that are not highlighted hold the value they had at
+    /// this time.
+    #[clap(long)]
+    pub at: Option<String>,
+    #[clap(flatten)]
+    pub copt: CommonOpt,
+}
+
 #[derive(Debug, Args)]
 pub struct LoginOpt {
     #[clap(flatten)]
@@ -901,6 +1545,10 @@ pub struct LoginOpt {
     value_parser = clap::builder::NonEmptyStringValueParser::new())]
     /// Supply a password to the login option
     password: Option<String>,
+    #[clap(long = "jwt-key", env = "KANIDM_JWT_KEY")]
+    /// Authenticate a service account with a jwt assertion signed by this ECDSA P-256 private
+    /// key in PEM format
+    jwt_key: Option<PathBuf>,
 }
 
 #[derive(Debug, Args)]
@@ -909,6 +1557,12 @@ pub struct ReauthOpt {
     copt: CommonOpt,
 }
 
+#[derive(Debug, Args)]
+pub struct ShellOpt {
+    #[clap(flatten)]
+    copt: CommonOpt,
+}
+
 #[derive(Debug, Args)]
 pub struct LogoutOpt {
     #[clap(flatten)]
@@ -918,6 +1572,42 @@ pub struct LogoutOpt {
     local_only: bool,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum BreakGlassOpt {
+    #[clap(name = "challenge")]
+    /// Request a challenge to be signed by the break glass key. The challenge is written to
+    /// the file, and the id to use with `login` is displayed.
+    Challenge {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        /// The file to write the challenge to.
+        #[clap(long = "out")]
+        out: PathBuf,
+    },
+    #[clap(name = "login")]
+    /// Submit the DER encoded signature of a challenge to login as the admin account.
+    Login {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        /// The id of the challenge that was signed.
+        #[clap(long = "id")]
+        id: Uuid,
+        /// The file containing the DER encoded ECDSA signature of the challenge.
+        #[clap(long = "signature")]
+        signature: PathBuf,
+    },
+    #[clap(name = "code")]
+    /// Login as an emergency account with one of its recovery codes. You will be prompted for
+    /// the code, which can't be used again.
+    Code {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        /// The name of the emergency account.
+        #[clap(long = "account")]
+        account: String,
+    },
+}
+
 #[derive(Debug, Subcommand)]
 pub enum SessionOpt {
     #[clap(name = "list")]
@@ -940,6 +1630,9 @@ pub struct FilterOpt {
 pub struct CreateOpt {
     #[clap(value_parser)]
     file: PathBuf,
+    /// Check the create and show the entries as they would be created, without creating them
+    #[clap(long = "dry-run")]
+    dry_run: bool,
     #[clap(flatten)]
     commonopts: CommonOpt,
 }
@@ -952,6 +1645,20 @@ pub struct ModifyOpt {
     filter: String,
     #[clap(value_parser)]
     file: PathBuf,
+    /// Check the modify and show the entries as they would be modified, without modifying them
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DeleteOpt {
+    #[clap()]
+    filter: String,
+    /// Check the delete and show the entries that would be deleted, without deleting them
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+    #[clap(flatten)]
+    commonopts: CommonOpt,
 }
 
 #[derive(Debug, Subcommand)]
@@ -963,7 +1670,131 @@ pub enum RawOpt {
     #[clap(name = "modify")]
     Modify(ModifyOpt),
     #[clap(name = "delete")]
-    Delete(FilterOpt),
+    Delete(DeleteOpt),
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum EntryFormat {
+    /// One json object for each entry, in the same form as `raw create`.
+    Json,
+    /// LDIF content records.
+    Ldif,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum LdifProfile {
+    /// Kanidm attribute and class names.
+    Kanidm,
+    /// The inetOrgPerson, groupOfNames and posix schema of OpenLDAP and 389 Directory Server.
+    #[value(alias = "389ds")]
+    Openldap,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportOpt {
+    /// The file to import entries from
+    #[clap(value_parser)]
+    pub file: PathBuf,
+    #[clap(long, value_enum, default_value = "json")]
+    pub format: EntryFormat,
+    /// How the attributes and object classes of an ldif file are mapped to kanidm
+    #[clap(long, value_enum, default_value = "kanidm")]
+    pub profile: LdifProfile,
+    /// A json file that defines how the attributes and object classes of an ldif file are
+    /// mapped to kanidm, instead of a profile
+    #[clap(long = "mapping", conflicts_with = "profile")]
+    pub mapping: Option<PathBuf>,
+    /// The number of entries to send to the server in each request
+    #[clap(long = "batch-size", default_value = "100")]
+    pub batch_size: usize,
+    #[clap(flatten)]
+    pub commonopts: CommonOpt,
+}
+
+#[derive(Debug, Args)]
+pub struct ApplyOpt {
+    /// A json file that describes the groups and oauth2 clients to manage
+    #[clap(short, long, value_parser)]
+    pub file: PathBuf,
+    /// Show the changes that are needed without applying them
+    #[clap(long = "dry-run")]
+    pub dry_run: bool,
+    #[clap(flatten)]
+    pub commonopts: CommonOpt,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportOpt {
+    #[clap(long, value_enum, default_value = "json")]
+    pub format: EntryFormat,
+    /// The file to write to. If not set, the entries are written to stdout
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+    /// The dn that ldif records are relative to, such as `dc=example,dc=com`
+    #[clap(long = "base-dn")]
+    pub base_dn: Option<String>,
+    #[clap(flatten)]
+    pub commonopts: CommonOpt,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EntryDiffOpt {
+    /// Compare an entry as it exists on the connected server and on another replica. Your
+    /// current session is used to authenticate to both servers.
+    #[clap(name = "replica")]
+    Replica {
+        /// The uuid of the entry to compare
+        uuid: Uuid,
+        /// The URL of the other replica
+        #[clap(long = "other-url")]
+        other_url: String,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+    /// Compare two saved copies of an entry, such as the json output of `kanidm raw search`.
+    /// If a file contains more than one entry, select the entry to compare with --uuid.
+    #[clap(name = "files")]
+    Files {
+        #[clap(value_parser)]
+        left: PathBuf,
+        #[clap(value_parser)]
+        right: PathBuf,
+        /// The uuid of the entry to compare
+        #[clap(long)]
+        uuid: Option<Uuid>,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DebugOpt {
+    /// Show the attribute level differences of an entry between two replicas or two saved
+    /// copies, to help investigate replication discrepancies.
+    #[clap(name = "entry-diff")]
+    EntryDiff {
+        #[clap(subcommand)]
+        commands: EntryDiffOpt,
+    },
+    /// Show the internal representation of an entry, including recycled entries and
+    /// tombstones, with the change that last modified each attribute. Requires system
+    /// administrator rights.
+    #[clap(name = "entry-inspect")]
+    EntryInspect {
+        /// The uuid or name of the entry to inspect
+        id: String,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+    /// Show the changes made to an entry that are still held in the audit log, and which
+    /// identity last changed each attribute. Requires system administrator rights.
+    #[clap(name = "entry-history")]
+    EntryHistory {
+        /// The uuid or name of the entry
+        id: String,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -973,6 +1804,57 @@ pub enum SelfOpt {
     IdentifyUser(CommonOpt),
     /// Show the current authenticated user's identity
     Whoami(CommonOpt),
+    /// Issue a short lived ssh user certificate for an OpenSSH format public key. The
+    /// certificate is written to standard output.
+    #[clap(name = "ssh-certificate")]
+    SshCertificate {
+        public_key_path: PathBuf,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Show the public keys of the certificate authorities that issue ssh user certificates.
+    /// These must be trusted by sshd with `TrustedUserCAKeys`.
+    #[clap(name = "show-ssh-ca-public-keys")]
+    ShowSshCaPublicKeys {
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Manage your application passwords. These are used in place of your password to bind
+    /// to an application with LDAP, such as from a mail client.
+    #[clap(name = "application-password")]
+    ApplicationPassword {
+        #[clap(subcommand)]
+        commands: SelfApplicationPasswordOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SelfApplicationPasswordOpt {
+    /// List your application passwords. The passwords themselves can't be shown again.
+    #[clap(name = "list")]
+    List(CommonOpt),
+    /// Generate a new application password. The password is only shown once.
+    #[clap(name = "generate")]
+    Generate {
+        /// The name of the application that the password is for.
+        #[clap(name = "application")]
+        application: String,
+        /// A label describing where the password is used, such as the device. An existing
+        /// password of the application with the same label is replaced.
+        #[clap(name = "label")]
+        label: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Revoke an application password. Other passwords of the application keep working.
+    #[clap(name = "revoke")]
+    Revoke {
+        /// The id of the password, as shown by `list`.
+        #[clap(name = "password-id")]
+        password_id: Uuid,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
 }
 
 #[derive(Debug, Args)]
@@ -1079,6 +1961,22 @@ pub enum Oauth2Opt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    #[clap(name = "create-native")]
+    /// Create a new OAuth2 public client for a native or mobile application. These clients
+    /// require PKCE, may redirect to a registered private-use scheme or to a registered
+    /// loopback address on any port, and are issued shorter lived tokens.
+    CreateNative {
+        #[clap(name = "name")]
+        name: String,
+        #[clap(name = "displayname")]
+        displayname: String,
+        /// The first redirect uri of the application, such as `com.example.app:/callback`
+        /// or `http://127.0.0.1/callback`.
+        #[clap(name = "origin")]
+        origin: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
     #[clap(name = "update-scope-map", visible_aliases=&["create-scope-map"])]
     /// Update or add a new mapping from a group to scopes that it provides to members
     UpdateScopeMap(Oauth2CreateScopeMapOpt),
@@ -1093,6 +1991,76 @@ pub enum Oauth2Opt {
     /// Remove a mapping from groups to scopes
     DeleteSupScopeMap(Oauth2DeleteScopeMapOpt),
 
+    #[clap(name = "add-scope-description")]
+    /// Add a human readable title and description of a scope, that is shown to users when they
+    /// are asked to consent to this client. Setting a language allows a translation to be
+    /// provided, which is displayed to users who prefer that language.
+    AddScopeDescription {
+        #[clap(flatten)]
+        nopt: Named,
+        #[clap(name = "scope")]
+        scope: String,
+        #[clap(name = "title")]
+        title: String,
+        #[clap(long)]
+        description: Option<String>,
+        /// A language tag such as `de` or `pt-BR`.
+        #[clap(long)]
+        language: Option<String>,
+    },
+    #[clap(name = "remove-scope-description")]
+    /// Remove the description of a scope. If a language is given, only the description in
+    /// that language is removed.
+    RemoveScopeDescription {
+        #[clap(flatten)]
+        nopt: Named,
+        #[clap(name = "scope")]
+        scope: String,
+        #[clap(long)]
+        language: Option<String>,
+    },
+
+    #[clap(name = "add-computed-claim")]
+    /// Add a claim whose values are computed from the account that is authorising. The values
+    /// are either a template of the account's attributes, such as `{name}@example.com`, or the
+    /// names of the account's groups.
+    AddComputedClaim {
+        #[clap(flatten)]
+        nopt: Named,
+        #[clap(name = "claim")]
+        claim: String,
+        /// A template of attributes, such as `{name}.{gidnumber}@example.com`.
+        #[clap(long, required_unless_present = "groups", conflicts_with = "groups")]
+        template: Option<String>,
+        /// Emit the names of the groups that the account is a member of.
+        #[clap(long)]
+        groups: bool,
+        /// Only retain values that start with this prefix.
+        #[clap(long)]
+        filter_prefix: Option<String>,
+        /// Only retain values that match this glob, where `*` matches any characters and `?`
+        /// matches a single character.
+        #[clap(long)]
+        filter_glob: Option<String>,
+        /// Remove this prefix from the values.
+        #[clap(long)]
+        strip_prefix: Option<String>,
+        /// Convert the values to lowercase.
+        #[clap(long)]
+        lowercase: bool,
+        /// Join the values with this separator into a single string. `array` emits a json array.
+        #[clap(long)]
+        join: Option<String>,
+    },
+    #[clap(name = "remove-computed-claim")]
+    /// Remove a computed claim.
+    RemoveComputedClaim {
+        #[clap(flatten)]
+        nopt: Named,
+        #[clap(name = "claim")]
+        claim: String,
+    },
+
     #[clap(name = "update-claim-map", visible_aliases=&["create-claim-map"])]
     /// Update or add a new mapping from a group to custom claims that it provides to members
     UpdateClaimMap {
@@ -1129,6 +2097,34 @@ pub enum Oauth2Opt {
     #[clap(name = "show-basic-secret")]
     /// Show the associated basic secret for this client
     ShowBasicSecret(Named),
+    #[clap(name = "rotate-basic-secret")]
+    /// Replace the basic secret of this client. The previous secret remains valid for a period
+    /// so that the client can be updated to use the new secret without an outage.
+    RotateBasicSecret {
+        #[clap(flatten)]
+        nopt: Named,
+        /// How many seconds the previous secret remains valid for. Defaults to 7 days.
+        #[clap(long)]
+        previous_validity: Option<u64>,
+    },
+    #[clap(name = "remove-previous-basic-secret")]
+    /// Remove the previous basic secret of this client, ending its validity immediately
+    RemovePreviousBasicSecret(Named),
+    #[clap(name = "set-basic-secret-expiry")]
+    /// Set the time after which the basic secret of this client is no longer valid
+    SetBasicSecretExpiry {
+        #[clap(flatten)]
+        nopt: Named,
+        #[clap(name = "datetime", verbatim_doc_comment)]
+        /// This accepts multiple options:
+        /// - An RFC3339 time of the format "YYYY-MM-DDTHH:MM:SS+TZ", "2020-09-25T11:22:02+10:00"
+        /// - One of "any", "clear" or "never" to remove the expiry.
+        /// - "now" to expire immediately
+        datetime: String,
+    },
+    #[clap(name = "list-sessions")]
+    /// List the sessions issued to this client by the client credentials grant
+    ListSessions(Named),
     #[clap(name = "delete")]
     /// Delete a oauth2 client
     Delete(Named),
@@ -1170,6 +2166,15 @@ pub enum Oauth2Opt {
     /// Removes the custom image previously set.
     #[clap(name = "remove-image")]
     RemoveImage(Named),
+    /// Set the group whose members may manage this client. Entry managers can update the
+    /// scope maps, claim maps, redirect urls and presentation of the client.
+    #[clap(name = "set-entry-manager")]
+    SetEntryManagedBy {
+        #[clap(flatten)]
+        nopt: Named,
+        /// The name or spn of the group that will manage this client.
+        entry_managed_by: String,
+    },
 
     /// Add a supplemental URL as a redirection target. For example a phone app
     /// may use a redirect URL such as `app://my-cool-app` to trigger a native
@@ -1192,6 +2197,77 @@ pub enum Oauth2Opt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    /// Allow this client to exchange a user's access token for a token to another
+    /// OAuth2 client, so that it can call that client on behalf of the user.
+    #[clap(name = "add-token-exchange-audience")]
+    AddTokenExchangeAudience {
+        #[clap(flatten)]
+        nopt: Named,
+        /// The name of the OAuth2 client that tokens may be exchanged for.
+        audience: String,
+    },
+    /// Prevent this client from exchanging tokens for another OAuth2 client.
+    #[clap(name = "remove-token-exchange-audience")]
+    RemoveTokenExchangeAudience {
+        #[clap(flatten)]
+        nopt: Named,
+        /// The name of the OAuth2 client that tokens may no longer be exchanged for.
+        audience: String,
+    },
+    /// Assign this client to an account or group. Once any member is assigned, only
+    /// assigned accounts and members of assigned groups may see and use this client.
+    #[clap(name = "add-assigned-member")]
+    AddAssignedMember {
+        #[clap(flatten)]
+        nopt: Named,
+        /// The name of the account or group to assign.
+        member: String,
+    },
+    /// Remove an assigned account or group from this client.
+    #[clap(name = "remove-assigned-member")]
+    RemoveAssignedMember {
+        #[clap(flatten)]
+        nopt: Named,
+        /// The name of the account or group to unassign.
+        member: String,
+    },
+    /// Allow this client to use a grant type at the token endpoint. Once any grant type is
+    /// added, only the allowed grant types may be used. By default all are allowed.
+    #[clap(name = "add-allowed-grant-type")]
+    AddAllowedGrantType {
+        #[clap(flatten)]
+        nopt: Named,
+        #[clap(name = "grant-type", value_enum)]
+        grant_type: Oauth2GrantType,
+    },
+    /// Remove a grant type from those this client may use. If no allowed grant types remain,
+    /// all grant types are allowed again.
+    #[clap(name = "remove-allowed-grant-type")]
+    RemoveAllowedGrantType {
+        #[clap(flatten)]
+        nopt: Named,
+        #[clap(name = "grant-type", value_enum)]
+        grant_type: Oauth2GrantType,
+    },
+    /// Allow this client to request a response type at the authorisation endpoint. Once any
+    /// response type is added, only the allowed response types may be used. By default all
+    /// are allowed.
+    #[clap(name = "add-allowed-response-type")]
+    AddAllowedResponseType {
+        #[clap(flatten)]
+        nopt: Named,
+        #[clap(name = "response-type", value_enum)]
+        response_type: Oauth2ResponseType,
+    },
+    /// Remove a response type from those this client may request. If no allowed response
+    /// types remain, all response types are allowed again.
+    #[clap(name = "remove-allowed-response-type")]
+    RemoveAllowedResponseType {
+        #[clap(flatten)]
+        nopt: Named,
+        #[clap(name = "response-type", value_enum)]
+        response_type: Oauth2ResponseType,
+    },
     #[clap(name = "enable-pkce")]
     /// Enable PKCE on this oauth2 client. This defaults to being enabled.
     EnablePkce(Named),
@@ -1199,6 +2275,14 @@ pub enum Oauth2Opt {
     /// may not support it. You should request the client to enable PKCE!
     #[clap(name = "warning-insecure-client-disable-pkce")]
     DisablePkce(Named),
+    #[clap(name = "enable-refresh-token-rotation")]
+    /// Enable refresh token rotation on this oauth2 client. This defaults to being enabled.
+    EnableRefreshTokenRotation(Named),
+    /// Disable refresh token rotation on this oauth2 client, allowing the same refresh token
+    /// to be used many times. This is only needed for clients that are unable to store the
+    /// new refresh token issued on each refresh.
+    #[clap(name = "warning-disable-refresh-token-rotation")]
+    DisableRefreshTokenRotation(Named),
     #[clap(name = "warning-enable-legacy-crypto")]
     /// Enable legacy signing crypto on this oauth2 client. This defaults to being disabled.
     /// You only need to enable this for openid clients that do not support modern cryptographic
@@ -1222,6 +2306,46 @@ pub enum Oauth2Opt {
         #[clap(flatten)]
         copt: CommonOpt,
     },
+    /// Set how redirect URLs are validated. Exact matching is the default. Native
+    /// applications may instead allow loopback redirect URLs on any port, or any path of
+    /// a registered private-use scheme such as `com.example.app:`.
+    #[clap(name = "set-redirect-url-mode")]
+    SetRedirectUriMode {
+        name: String,
+        #[clap(name = "mode", value_enum)]
+        mode: Oauth2RedirectUriMode,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the algorithm that this client's tokens are signed with. The client is given
+    /// its own signing keys which can be rotated and revoked independently of other clients.
+    #[clap(name = "set-token-signing-alg")]
+    SetTokenSigningAlg {
+        name: String,
+        #[clap(name = "alg", value_enum)]
+        sign_alg: Oauth2JwtSignAlg,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Rotate the signing keys of this client. The new key is used from the given time, or
+    /// immediately if no time is given. Previous keys are still published for verification.
+    #[clap(name = "rotate-keys")]
+    RotateKeys {
+        name: String,
+        /// An RFC3339 timestamp such as "2025-01-01T00:00:00+00:00"
+        #[clap(name = "rotate-at")]
+        rotate_at: Option<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Revoke a signing key of this client. A new key is generated if it was active.
+    #[clap(name = "revoke-key")]
+    RevokeKey {
+        name: String,
+        key_id: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
     #[clap(name = "enable-localhost-redirects")]
     /// Allow public clients to redirect to localhost.
     EnablePublicLocalhost {
@@ -1313,46 +2437,659 @@ pub enum DeniedNamesOpt {
 }
 
 #[derive(Debug, Subcommand)]
-pub enum DomainOpt {
-    #[clap[name = "set-displayname"]]
-    /// Set the domain display name
-    SetDisplayname(OptSetDomainDisplayname),
-    /// Sets the maximum number of LDAP attributes that can be queried in one operation.
-    #[clap[name = "set-ldap-queryable-attrs"]]
-    SetLdapMaxQueryableAttrs {
-        #[clap(flatten)]
-        copt: CommonOpt,
-        #[clap(name = "maximum-queryable-attrs")]
-        new_max_queryable_attrs: usize,
-    },
-    #[clap[name = "set-ldap-basedn"]]
-    /// Change the basedn of this server. Takes effect after a server restart.
-    /// Examples are `o=organisation` or `dc=domain,dc=name`. Must be a valid ldap
-    /// dn containing only alphanumerics, and dn components must be org (o), domain (dc) or
-    /// orgunit (ou).
-    SetLdapBasedn {
-        #[clap(flatten)]
-        copt: CommonOpt,
-        #[clap(name = "new-basedn")]
-        new_basedn: String,
-    },
-    /// Enable or disable unix passwords being used to bind via LDAP. Unless you have a specific
-    /// requirement for this, you should disable this.
-    SetLdapAllowUnixPasswordBind {
+pub enum AccessControlOpt {
+    #[clap(name = "preview")]
+    /// Compare the access of a sample of accounts between the enforced access controls and
+    /// the staged ones
+    Preview {
         #[clap(flatten)]
         copt: CommonOpt,
-        #[clap(name = "allow", action = clap::ArgAction::Set)]
-        enable: bool,
+        /// How many accounts and entries to sample
+        #[clap(long)]
+        sample: Option<usize>,
     },
-    /// Enable or disable easter eggs in the server. This includes seasonal icons, kanidm
-    /// birthday surprises and other fun components. Defaults to false for production releases
-    /// and true in development builds.
-    SetAllowEasterEggs {
-        #[clap(flatten)]
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrationsOpt {
+    #[clap(name = "list")]
+    /// List the migrations that have been applied to the database, oldest first
+    List(CommonOpt),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WebhookOpt {
+    #[clap(name = "list")]
+    /// List all webhooks
+    List(CommonOpt),
+    #[clap(name = "get")]
+    /// Display a selected webhook
+    Get(Named),
+    #[clap(name = "create")]
+    /// Create a new webhook. Filters select which entry changes are sent to the webhook, in
+    /// the form 'action:class[:attribute]' where action is one of created, modified or
+    /// deleted, and class may be '*' to match any entry. For example 'created:person' or
+    /// 'modified:group:member'.
+    Create {
+        #[clap(name = "name")]
+        name: String,
+        #[clap(name = "url")]
+        url: Url,
+        #[clap(long)]
+        description: Option<String>,
+        #[clap(long = "filter")]
+        filters: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "delete")]
+    /// Delete a webhook
+    Delete(Named),
+    #[clap(name = "set-url")]
+    /// Change the url that events are sent to
+    SetUrl {
+        name: String,
+        #[clap(name = "url")]
+        url: Url,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "add-filter")]
+    /// Add filters selecting the entry changes that are sent to the webhook
+    AddFilter {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        filters: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "remove-filter")]
+    /// Remove filters from the webhook
+    RemoveFilter {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        filters: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "show-secret")]
+    /// Show the secret that the webhook uses to verify the signature of events
+    ShowSecret(Named),
+    #[clap(name = "rotate-secret")]
+    /// Generate a new secret for the webhook. The receiver must be updated to use the new
+    /// secret, as events are immediately signed with it.
+    RotateSecret(Named),
+    #[clap(name = "deliveries")]
+    /// Show the status of the most recent events sent to the webhook
+    Deliveries(Named),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AnnouncementOpt {
+    #[clap(name = "list")]
+    /// List all announcements, including those that have not started or have ended
+    List(CommonOpt),
+    #[clap(name = "get")]
+    /// Display a selected announcement
+    Get(Named),
+    #[clap(name = "create")]
+    /// Publish an announcement that is shown as a banner in the web interface. Times are in
+    /// RFC3339 format, for example 2025-06-01T10:00:00+10:00. Without a start time it is shown
+    /// immediately, and without an end time it is shown until it is deleted.
+    Create {
+        #[clap(name = "name")]
+        name: String,
+        #[clap(name = "message")]
+        message: String,
+        /// One of info, warning or critical
+        #[clap(long, default_value = "info")]
+        severity: String,
+        #[clap(long)]
+        start: Option<String>,
+        #[clap(long)]
+        end: Option<String>,
+        /// Also show the announcement after logging in with the command line tools
+        #[clap(long)]
+        cli: bool,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "set-message")]
+    /// Change the message of an announcement
+    SetMessage {
+        name: String,
+        #[clap(name = "message")]
+        message: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "delete")]
+    /// Delete an announcement
+    Delete(Named),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum OidcUpstreamOpt {
+    #[clap(name = "list")]
+    /// List all upstream OpenID Connect providers
+    List(CommonOpt),
+    #[clap(name = "get")]
+    /// Display a selected upstream provider
+    Get(Named),
+    #[clap(name = "create")]
+    /// Allow people to login with an upstream OpenID Connect provider, such as Azure AD. The
+    /// endpoints of the provider are found from the discovery document of the issuer. The
+    /// provider must allow the redirect url <origin>/ui/login/federated/callback.
+    Create {
+        #[clap(name = "name")]
+        name: String,
+        #[clap(name = "displayname")]
+        displayname: String,
+        #[clap(name = "issuer")]
+        issuer: Url,
+        #[clap(name = "client-id")]
+        client_id: String,
+        /// The client secret. If not provided you will be prompted for it.
+        #[clap(long)]
+        client_secret: Option<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "set-client-secret")]
+    /// Replace the client secret of an upstream provider. You will be prompted for it.
+    SetClientSecret(Named),
+    #[clap(name = "delete")]
+    /// Delete an upstream provider
+    Delete(Named),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Saml2Opt {
+    #[clap(name = "list")]
+    /// List all SAML service providers
+    List(CommonOpt),
+    #[clap(name = "get")]
+    /// Display a selected service provider
+    Get(Named),
+    #[clap(name = "create")]
+    /// Create a SAML service provider. Only members of groups added with update-group-map
+    /// may sign in to it.
+    Create {
+        #[clap(name = "name")]
+        name: String,
+        #[clap(name = "displayname")]
+        displayname: String,
+        /// The entity id of the service provider, from its metadata
+        #[clap(name = "entity-id")]
+        entity_id: String,
+        /// The url the service provider receives assertions at
+        #[clap(name = "acs-url")]
+        acs_url: Url,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "set-acs-urls")]
+    /// Replace the urls the service provider may receive assertions at
+    SetAcsUrls {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        acs_urls: Vec<Url>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "update-group-map")]
+    /// Allow the members of a group to sign in, with these values in their groups attribute
+    UpdateGroupMap {
+        name: String,
+        group: String,
+        values: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "delete-group-map")]
+    /// Remove the group map of a group, so that its members can no longer sign in
+    DeleteGroupMap {
+        name: String,
+        group: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "metadata")]
+    /// Display the identity provider metadata to configure the service provider with
+    Metadata(Named),
+    #[clap(name = "revoke-key")]
+    /// Revoke a signing key of the service provider. A new key is generated if it was active.
+    RevokeKey {
+        name: String,
+        key_id: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "delete")]
+    /// Delete a service provider
+    Delete(Named),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HbacOpt {
+    #[clap(name = "list")]
+    /// List all host based access control rules
+    List(CommonOpt),
+    #[clap(name = "get")]
+    /// Display a selected rule
+    Get(Named),
+    #[clap(name = "create")]
+    /// Create a new rule. Rules allow access unless --deny is given, and apply to all hosts
+    /// and pam services until they are limited with add-host or add-service.
+    Create {
+        #[clap(name = "name")]
+        name: String,
+        #[clap(long)]
+        description: Option<String>,
+        /// Deny rather than allow access to the members of this rule
+        #[clap(long)]
+        deny: bool,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "delete")]
+    /// Delete a rule
+    Delete(Named),
+    #[clap(name = "add-member")]
+    /// Add accounts or groups that the rule applies to
+    AddMember {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        members: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "remove-member")]
+    /// Remove accounts or groups from the rule
+    RemoveMember {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        members: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "add-host")]
+    /// Limit the rule to the named hosts
+    AddHost {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        hosts: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "remove-host")]
+    /// Remove hosts from the rule. If no hosts remain the rule applies to all hosts.
+    RemoveHost {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        hosts: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "add-host-group")]
+    /// Limit the rule to the machines of the named host groups
+    AddHostGroup {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        host_groups: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "remove-host-group")]
+    /// Remove host groups from the rule. If no hosts or host groups remain the rule applies
+    /// to all hosts.
+    RemoveHostGroup {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        host_groups: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "add-service")]
+    /// Limit the rule to the named pam services, such as sshd or login
+    AddService {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        services: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "remove-service")]
+    /// Remove pam services from the rule. If no services remain the rule applies to all
+    /// services.
+    RemoveService {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        services: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HostOpt {
+    #[clap(name = "list")]
+    /// List all hosts
+    List(CommonOpt),
+    #[clap(name = "get")]
+    /// Display a selected host
+    Get(Named),
+    #[clap(name = "create")]
+    /// Create a new host. A join token must be issued before a machine can enroll as the host
+    Create {
+        #[clap(name = "name")]
+        name: String,
+        #[clap(long)]
+        /// Defaults to the name of the host
+        displayname: Option<String>,
+        #[clap(long)]
+        description: Option<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "delete")]
+    /// Delete a host, revoking its credential
+    Delete(Named),
+    #[clap(name = "generate-join-token")]
+    /// Issue a one time join token that allows a machine to enroll as the host. Any token
+    /// that was previously issued for the host is replaced.
+    GenerateJoinToken {
+        name: String,
+        #[clap(long)]
+        /// The number of seconds that the token is valid for. Defaults to one hour.
+        ttl: Option<u64>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "set-kiosk-mode")]
+    /// Mark the host as a shared workstation. Accounts are only cached for a short time,
+    /// every login and unlock must be confirmed by the server, and the home directories of
+    /// guests are removed when they log out.
+    SetKioskMode {
+        name: String,
+        #[clap(name = "enable", action = clap::ArgAction::Set)]
+        enable: bool,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "set-kiosk-cache-timeout")]
+    /// Set the number of seconds that a kiosk may cache accounts for
+    SetKioskCacheTimeout {
+        name: String,
+        timeout: u32,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "reset-kiosk-cache-timeout")]
+    /// Reset the cache timeout of a kiosk to the default
+    ResetKioskCacheTimeout(Named),
+    #[clap(name = "add-kiosk-guest-group")]
+    /// Members of these groups are guests of the kiosk, and have their home directories
+    /// removed when they log out
+    AddKioskGuestGroup {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        groups: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "remove-kiosk-guest-group")]
+    /// Remove guest groups from the kiosk
+    RemoveKioskGuestGroup {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        groups: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "add-kerberos-service-principal")]
+    /// Add kerberos service principal names, such as `nfs/files.example.com`, to the host.
+    /// The host can then fetch a keytab for these services, and accounts that log in to
+    /// enrolled hosts can be issued tickets for them.
+    AddKerberosServicePrincipal {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        principals: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "remove-kerberos-service-principal")]
+    /// Remove kerberos service principal names from the host
+    RemoveKerberosServicePrincipal {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        principals: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HostGroupOpt {
+    #[clap(name = "list")]
+    /// List all host groups
+    List(CommonOpt),
+    #[clap(name = "get")]
+    /// Display a selected host group
+    Get(Named),
+    #[clap(name = "create")]
+    /// Create a new host group
+    Create {
+        #[clap(name = "name")]
+        name: String,
+        #[clap(long)]
+        description: Option<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "delete")]
+    /// Delete a host group
+    Delete(Named),
+    #[clap(name = "add-member")]
+    /// Add machine accounts to the host group
+    AddMember {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        members: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "remove-member")]
+    /// Remove machine accounts from the host group
+    RemoveMember {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        members: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "add-tag")]
+    /// Include all machine accounts with any of these host tags in the host group
+    AddTag {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        tags: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "remove-tag")]
+    /// Stop including machine accounts with these host tags in the host group
+    RemoveTag {
+        name: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        tags: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "tag-machine")]
+    /// Add host tags to a machine account
+    TagMachine {
+        account_id: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        tags: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap(name = "untag-machine")]
+    /// Remove host tags from a machine account
+    UntagMachine {
+        account_id: String,
+        #[clap(value_parser, required = true, num_args(1..))]
+        tags: Vec<String>,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DeniedPasswordTermsOpt {
+    #[clap[name = "show"]]
+    /// Show the terms that passwords may not contain
+    Show {
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    #[clap[name = "append"]]
+    /// Add terms that passwords may not contain, such as company or product names.
+    Append {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(value_parser, required = true, num_args(1..))]
+        terms: Vec<String>,
+    },
+    #[clap[name = "remove"]]
+    /// Remove terms from the denied password term list.
+    Remove {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(value_parser, required = true, num_args(1..))]
+        terms: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DomainOpt {
+    #[clap[name = "set-displayname"]]
+    /// Set the domain display name
+    SetDisplayname(OptSetDomainDisplayname),
+    /// Sets the maximum number of LDAP attributes that can be queried in one operation.
+    #[clap[name = "set-ldap-queryable-attrs"]]
+    SetLdapMaxQueryableAttrs {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(name = "maximum-queryable-attrs")]
+        new_max_queryable_attrs: usize,
+    },
+    #[clap[name = "set-ldap-basedn"]]
+    /// Change the basedn of this server. Takes effect after a server restart.
+    /// Examples are `o=organisation` or `dc=domain,dc=name`. Must be a valid ldap
+    /// dn containing only alphanumerics, and dn components must be org (o), domain (dc) or
+    /// orgunit (ou).
+    SetLdapBasedn {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(name = "new-basedn")]
+        new_basedn: String,
+    },
+    /// Enable or disable unix passwords being used to bind via LDAP. Unless you have a specific
+    /// requirement for this, you should disable this.
+    SetLdapAllowUnixPasswordBind {
+        #[clap(flatten)]
         copt: CommonOpt,
         #[clap(name = "allow", action = clap::ArgAction::Set)]
         enable: bool,
     },
+    /// Enable or disable matching of nested group members in LDAP member filters. This is
+    /// for applications that expect group membership to be resolved by the LDAP server.
+    SetLdapGroupCompat {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(name = "enable", action = clap::ArgAction::Set)]
+        enable: bool,
+    },
+    /// Enable or disable easter eggs in the server. This includes seasonal icons, kanidm
+    /// birthday surprises and other fun components. Defaults to false for production releases
+    /// and true in development builds.
+    SetAllowEasterEggs {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(name = "allow", action = clap::ArgAction::Set)]
+        enable: bool,
+    },
+    /// Set the default colour theme of the web interface. People may choose their own theme
+    /// in their profile settings, which overrides this default.
+    SetTheme {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(name = "theme", value_enum)]
+        theme: UiTheme,
+    },
+    /// Set a message, such as a legal notice, that is shown on the login pages of the web
+    /// interface.
+    #[clap(name = "set-login-banner")]
+    SetLoginBanner {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(name = "banner")]
+        banner: String,
+    },
+    /// Remove the message that is shown on the login pages of the web interface.
+    #[clap(name = "remove-login-banner")]
+    RemoveLoginBanner {
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Set the primary colour of the web interface, which is used for buttons and other
+    /// highlights. Must be a hex colour such as `#1f6feb`.
+    #[clap(name = "set-primary-colour")]
+    SetPrimaryColour {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(name = "colour")]
+        colour: String,
+    },
+    /// Remove the primary colour of the web interface, reverting to the default.
+    #[clap(name = "remove-primary-colour")]
+    RemovePrimaryColour {
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Restrict anonymous searches of the white pages to these networks, in CIDR notation
+    /// such as `10.0.0.0/8`. Anonymous must be a member of `idm_white_pages_readers` for the
+    /// white pages to be searched.
+    #[clap(name = "set-white-pages-trusted-networks")]
+    SetWhitePagesTrustedNetworks {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(name = "networks", required = true)]
+        networks: Vec<String>,
+    },
+    /// Allow anonymous to search the white pages from any network.
+    #[clap(name = "remove-white-pages-trusted-networks")]
+    RemoveWhitePagesTrustedNetworks {
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Limit the number of white pages searches that anonymous may make from one address
+    /// each minute.
+    #[clap(name = "set-white-pages-rate-limit")]
+    SetWhitePagesRateLimit {
+        #[clap(flatten)]
+        copt: CommonOpt,
+        #[clap(name = "searches-per-minute")]
+        rate_limit: u32,
+    },
+    /// Remove the limit on white pages searches by anonymous.
+    #[clap(name = "remove-white-pages-rate-limit")]
+    RemoveWhitePagesRateLimit {
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
     #[clap(name = "show")]
     /// Show information about this system's domain
     Show(CommonOpt),
@@ -1442,6 +3179,23 @@ pub enum SynchOpt {
         #[clap(name = "attributes")]
         attrs: Vec<String>,
     },
+    /// List the attributes of synchronised entries where the sync source disagrees with a
+    /// value that has had its authority yielded to kanidm. The value in kanidm is retained.
+    #[clap(name = "list-conflicts")]
+    ListConflicts {
+        #[clap()]
+        account_id: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
+    /// Clear the conflicts of this sync account once they have been reviewed.
+    #[clap(name = "clear-conflicts")]
+    ClearConflicts {
+        #[clap()]
+        account_id: String,
+        #[clap(flatten)]
+        copt: CommonOpt,
+    },
     /// Reset the sync cookie of this connector, so that on the next operation of the sync tool
     /// a full refresh of the provider is requested. Kanidm attributes that have been granted
     /// authority will *not* be lost or deleted.
@@ -1541,12 +3295,60 @@ pub enum SystemOpt {
         #[clap(subcommand)]
         commands: DeniedNamesOpt,
     },
+    #[clap(name = "denied-password-terms")]
+    /// Configure and manage the terms that passwords may not contain
+    DeniedPasswordTerms {
+        #[clap(subcommand)]
+        commands: DeniedPasswordTermsOpt,
+    },
     #[clap(name = "oauth2")]
     /// Configure and display oauth2/oidc client configuration
     Oauth2 {
         #[clap(subcommand)]
         commands: Oauth2Opt,
     },
+    #[clap(name = "webhook")]
+    /// Configure webhooks that are sent events when entries change
+    Webhook {
+        #[clap(subcommand)]
+        commands: WebhookOpt,
+    },
+    #[clap(name = "announcement")]
+    /// Publish announcements that are shown to everyone using the instance
+    Announcement {
+        #[clap(subcommand)]
+        commands: AnnouncementOpt,
+    },
+    #[clap(name = "oidc-upstream")]
+    /// Configure upstream OpenID Connect providers that people can login with
+    OidcUpstream {
+        #[clap(subcommand)]
+        commands: OidcUpstreamOpt,
+    },
+    #[clap(name = "saml2")]
+    /// Configure SAML service providers that people can sign in to
+    Saml2 {
+        #[clap(subcommand)]
+        commands: Saml2Opt,
+    },
+    #[clap(name = "hbac")]
+    /// Configure host based access control rules for unix clients
+    Hbac {
+        #[clap(subcommand)]
+        commands: HbacOpt,
+    },
+    #[clap(name = "host")]
+    /// Create hosts and issue the join tokens that unix clients enroll with
+    Host {
+        #[clap(subcommand)]
+        commands: HostOpt,
+    },
+    #[clap(name = "host-group")]
+    /// Configure host groups and the host tags of machine accounts
+    HostGroup {
+        #[clap(subcommand)]
+        commands: HostGroupOpt,
+    },
     #[clap(name = "domain")]
     /// Configure and display domain configuration
     Domain {
@@ -1559,6 +3361,18 @@ pub enum SystemOpt {
         #[clap(subcommand)]
         commands: SynchOpt,
     },
+    #[clap(name = "migrations")]
+    /// Display the migrations that have been applied to the database
+    Migrations {
+        #[clap(subcommand)]
+        commands: MigrationsOpt,
+    },
+    #[clap(name = "access-control")]
+    /// Preview the impact of staged access control changes
+    AccessControl {
+        #[clap(subcommand)]
+        commands: AccessControlOpt,
+    },
     #[clap(name = "api")]
     /// API related things
     Api {
@@ -1576,6 +3390,13 @@ pub enum KanidmClientOpt {
     Reauth(ReauthOpt),
     /// Logout of an active cli session
     Logout(LogoutOpt),
+    /// Emergency login to the admin account with the break glass key, or to an emergency
+    /// account with a recovery code
+    #[clap(name = "break-glass")]
+    BreakGlass {
+        #[clap(subcommand)]
+        commands: BreakGlassOpt,
+    },
     /// Manage active cli sessions
     Session {
         #[clap(subcommand)]
@@ -1617,12 +3438,28 @@ pub enum KanidmClientOpt {
         #[clap(subcommand)]
         commands: RecycleOpt,
     },
+    /// Tools to help investigate server and replication issues
+    Debug {
+        #[clap(subcommand)]
+        commands: DebugOpt,
+    },
+    /// Import people and groups from a json or ldif file
+    Import(ImportOpt),
+    /// Export people and groups to a json or ldif file
+    Export(ExportOpt),
+    /// Apply a declarative description of groups, oauth2 clients and account policy, changing
+    /// only what differs from the server
+    Apply(ApplyOpt),
     /// Unsafe - low level, raw database queries and operations.
     #[clap(hide = true)]
     Raw {
         #[clap(subcommand)]
         commands: RawOpt,
     },
+    /// Start an interactive shell that runs commands in a single authenticated session. Entity
+    /// names and attributes can be tab completed. Commands are read from stdin when it isn't
+    /// a terminal, allowing multiple commands to be batched.
+    Shell(ShellOpt),
     /// Print the program version and exit
     Version {},
 }
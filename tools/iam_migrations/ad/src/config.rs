@@ -0,0 +1,140 @@
+use kanidm_proto::attribute::Attribute;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use url::Url;
+use uuid::Uuid;
+
+use ldap3_client::proto::LdapFilter;
+
+use crate::idmap::{IDMAP_RANGE_MAX, IDMAP_RANGE_MIN, IDMAP_RANGE_SIZE};
+
+/// People and groups, excluding the builtin accounts and groups of the domain such as
+/// krbtgt and Domain Admins.
+fn ad_filter() -> LdapFilter {
+    LdapFilter::And(vec![
+        LdapFilter::Or(vec![
+            LdapFilter::And(vec![
+                LdapFilter::Equality("objectcategory".to_string(), "person".to_string()),
+                LdapFilter::Equality("objectclass".to_string(), "user".to_string()),
+            ]),
+            LdapFilter::Equality("objectclass".to_string(), "group".to_string()),
+        ]),
+        LdapFilter::Not(Box::new(LdapFilter::Equality(
+            "iscriticalsystemobject".to_string(),
+            "TRUE".to_string(),
+        ))),
+    ])
+}
+
+// Attribute names are provided in lowercase by the ldap client.
+const AD_ATTR_SAMACCOUNTNAME: &str = "samaccountname";
+
+fn person_attr_user_name() -> String {
+    AD_ATTR_SAMACCOUNTNAME.to_string()
+}
+
+fn person_attr_display_name() -> String {
+    Attribute::DisplayName.to_string()
+}
+
+fn person_attr_mail() -> String {
+    Attribute::Mail.to_string()
+}
+
+fn person_attr_login_shell() -> String {
+    Attribute::LoginShell.to_string()
+}
+
+fn person_attr_ssh_public_key() -> String {
+    Attribute::LdapSshPublicKey.to_string()
+}
+
+fn group_attr_name() -> String {
+    AD_ATTR_SAMACCOUNTNAME.to_string()
+}
+
+fn group_attr_description() -> String {
+    Attribute::Description.to_string()
+}
+
+fn idmap_range_min() -> u32 {
+    IDMAP_RANGE_MIN
+}
+
+fn idmap_range_max() -> u32 {
+    IDMAP_RANGE_MAX
+}
+
+fn idmap_range_size() -> u32 {
+    IDMAP_RANGE_SIZE
+}
+
+/// Where the gidnumber of people and groups is taken from.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GidNumberSource {
+    /// Derive the gidnumber from the objectSid of the entry.
+    #[default]
+    Sid,
+    /// Use the uidNumber and gidNumber attributes of "Identity Management for UNIX".
+    Rfc2307,
+    /// Don't sync gidnumbers.
+    None,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    pub sync_token: String,
+    pub schedule: Option<String>,
+    pub status_bind: Option<String>,
+    pub ad_uri: Url,
+    pub ad_ca: String,
+    pub ad_sync_dn: String,
+    pub ad_sync_pw: String,
+    pub ad_sync_base_dn: String,
+
+    #[serde(default = "ad_filter")]
+    pub ad_filter: LdapFilter,
+
+    #[serde(default)]
+    pub gidnumber_source: GidNumberSource,
+    #[serde(default = "idmap_range_min")]
+    pub idmap_range_min: u32,
+    #[serde(default = "idmap_range_max")]
+    pub idmap_range_max: u32,
+    #[serde(default = "idmap_range_size")]
+    pub idmap_range_size: u32,
+
+    #[serde(default = "person_attr_user_name")]
+    pub person_attr_user_name: String,
+    #[serde(default = "person_attr_display_name")]
+    pub person_attr_display_name: String,
+    #[serde(default = "person_attr_mail")]
+    pub person_attr_mail: String,
+    #[serde(default = "person_attr_login_shell")]
+    pub person_attr_login_shell: String,
+    #[serde(default = "person_attr_ssh_public_key")]
+    pub person_attr_ssh_public_key: String,
+
+    #[serde(default = "group_attr_name")]
+    pub group_attr_name: String,
+    #[serde(default = "group_attr_description")]
+    pub group_attr_description: String,
+
+    #[serde(flatten)]
+    pub entry_map: BTreeMap<Uuid, EntryConfig>,
+
+    /// Maximum LDAP message size (in kilobytes)
+    pub max_ber_size: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct EntryConfig {
+    // Default false
+    #[serde(default)]
+    pub exclude: bool,
+
+    pub map_uuid: Option<Uuid>,
+    pub map_name: Option<String>,
+    pub map_gidnumber: Option<u32>,
+}
@@ -0,0 +1,10 @@
+#[derive(Clone, Debug)]
+pub enum SyncError {
+    ClientConfig,
+    LdapConn,
+    LdapAuth,
+    LdapSearch,
+    SyncStatus,
+    SyncUpdate,
+    Preprocess,
+}
@@ -0,0 +1,289 @@
+//! Conversion of the binary and windows specific values of Active Directory. Gidnumbers are
+//! derived from the objectSid of an entry with the same algorithm as the SSSD ldap_id_mapping
+//! option, so that hosts that were joined to the domain with SSSD keep the same ids.
+
+use base64::engine::general_purpose;
+use base64::Engine;
+use chrono::DateTime;
+use std::fmt;
+use uuid::Uuid;
+
+/// The defaults of SSSD ldap_idmap_range_min, ldap_idmap_range_max and ldap_idmap_range_size.
+pub const IDMAP_RANGE_MIN: u32 = 200_000;
+pub const IDMAP_RANGE_MAX: u32 = 2_000_200_000;
+pub const IDMAP_RANGE_SIZE: u32 = 200_000;
+
+/// The seed SSSD uses to hash a domain sid to its slice.
+const IDMAP_SLICE_SEED: u32 = 0xdead_beef;
+
+/// The userAccountControl flag of a disabled account.
+const UF_ACCOUNTDISABLE: u32 = 0x0002;
+
+/// Seconds between the windows epoch (1601) and the unix epoch.
+const FILETIME_UNIX_EPOCH_SECS: i64 = 11_644_473_600;
+const FILETIME_TICKS_PER_SEC: i64 = 10_000_000;
+
+/// Binary attributes are provided base64 encoded.
+pub fn decode_binary(value: &str) -> Option<Vec<u8>> {
+    general_purpose::STANDARD
+        .decode(value)
+        .or_else(|_| general_purpose::STANDARD_NO_PAD.decode(value))
+        .or_else(|_| general_purpose::URL_SAFE.decode(value))
+        .or_else(|_| general_purpose::URL_SAFE_NO_PAD.decode(value))
+        .ok()
+}
+
+/// The objectGUID of an entry. The first three fields are little endian.
+pub fn guid_from_bytes(bytes: &[u8]) -> Option<Uuid> {
+    Uuid::from_slice_le(bytes).ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sid {
+    revision: u8,
+    authority: u64,
+    sub_authorities: Vec<u32>,
+}
+
+impl Sid {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&revision, rest) = bytes.split_first()?;
+        let (&count, rest) = rest.split_first()?;
+        let (authority, rest) = rest.split_at_checked(6)?;
+
+        let authority = authority
+            .iter()
+            .fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+
+        if rest.len() != usize::from(count) * 4 {
+            return None;
+        }
+
+        let sub_authorities = rest
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+
+        Some(Sid {
+            revision,
+            authority,
+            sub_authorities,
+        })
+    }
+
+    /// The relative identifier of the entry within its domain.
+    pub fn rid(&self) -> Option<u32> {
+        self.sub_authorities.last().copied()
+    }
+
+    /// The sid of the domain that the entry belongs to.
+    pub fn domain(&self) -> Sid {
+        let mut domain = self.clone();
+        domain.sub_authorities.pop();
+        domain
+    }
+}
+
+impl fmt::Display for Sid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "S-{}-{}", self.revision, self.authority)?;
+        for sub_authority in self.sub_authorities.iter() {
+            write!(f, "-{}", sub_authority)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IdMap {
+    range_min: u32,
+    range_max: u32,
+    range_size: u32,
+}
+
+impl IdMap {
+    pub fn new(range_min: u32, range_max: u32, range_size: u32) -> Option<Self> {
+        if range_size == 0 || range_max <= range_min || range_max - range_min < range_size {
+            return None;
+        }
+        Some(IdMap {
+            range_min,
+            range_max,
+            range_size,
+        })
+    }
+
+    /// Each domain is given a slice of the id range, chosen by the hash of its sid. The id
+    /// is then the rid of the entry within that slice.
+    pub fn sid_to_gidnumber(&self, sid: &Sid) -> Option<u32> {
+        let rid = sid.rid()?;
+        if rid >= self.range_size {
+            return None;
+        }
+
+        let slices = (self.range_max - self.range_min) / self.range_size;
+        let slice = murmur3_32(sid.domain().to_string().as_bytes(), IDMAP_SLICE_SEED) % slices;
+
+        self.range_min
+            .checked_add(slice.checked_mul(self.range_size)?)?
+            .checked_add(rid)
+    }
+}
+
+fn murmur3_32(data: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+
+    let scramble = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut h = seed;
+    let mut chunks = data.chunks_exact(4);
+    for c in chunks.by_ref() {
+        h ^= scramble(u32::from_le_bytes([c[0], c[1], c[2], c[3]]));
+        h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+
+    let tail = chunks
+        .remainder()
+        .iter()
+        .rev()
+        .fold(0u32, |acc, b| (acc << 8) | u32::from(*b));
+    if !chunks.remainder().is_empty() {
+        h ^= scramble(tail);
+    }
+
+    h ^= data.len() as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+pub fn account_disabled(user_account_control: &str) -> bool {
+    user_account_control
+        .parse::<u32>()
+        .map(|uac| uac & UF_ACCOUNTDISABLE != 0)
+        .unwrap_or_default()
+}
+
+/// Convert accountExpires, a count of 100ns intervals since 1601, to rfc3339. Accounts that
+/// never expire have a value of 0 or i64::MAX.
+pub fn account_expires_to_rfc3339(account_expires: &str) -> Option<String> {
+    let ticks = account_expires.parse::<i64>().ok()?;
+    if ticks <= 0 || ticks == i64::MAX {
+        return None;
+    }
+
+    let secs = ticks / FILETIME_TICKS_PER_SEC - FILETIME_UNIX_EPOCH_SECS;
+    DateTime::from_timestamp(secs, 0).map(|dt| dt.to_rfc3339())
+}
+
+/// Kanidm names are more restricted than sAMAccountName, so characters that can't be used
+/// are replaced.
+pub fn to_kanidm_name(name: &str) -> String {
+    let name: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if name.starts_with(|c: char| c.is_ascii_lowercase()) {
+        name
+    } else {
+        format!("ad_{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // S-1-5-21-1004336348-1177238915-682003330-512
+    const DOMAIN_ADMINS_SID: &[u8] = &[
+        0x01, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x15, 0x00, 0x00, 0x00, 0xdc, 0xf4, 0xdc,
+        0x3b, 0x83, 0x3d, 0x2b, 0x46, 0x82, 0x8b, 0xa6, 0x28, 0x00, 0x02, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_sid_from_bytes() {
+        let sid = Sid::from_bytes(DOMAIN_ADMINS_SID).expect("Invalid sid");
+        assert_eq!(
+            sid.to_string(),
+            "S-1-5-21-1004336348-1177238915-682003330-512"
+        );
+        assert_eq!(sid.rid(), Some(512));
+        assert_eq!(
+            sid.domain().to_string(),
+            "S-1-5-21-1004336348-1177238915-682003330"
+        );
+
+        assert!(Sid::from_bytes(&DOMAIN_ADMINS_SID[..20]).is_none());
+        assert!(Sid::from_bytes(&[]).is_none());
+    }
+
+    #[test]
+    fn test_murmur3() {
+        assert_eq!(murmur3_32(b"", 0), 0);
+        assert_eq!(murmur3_32(b"", 1), 0x514e_28b7);
+        assert_eq!(murmur3_32(b"hello", 0), 0x248b_fa47);
+        assert_eq!(
+            murmur3_32(b"The quick brown fox jumps over the lazy dog", 0),
+            0x2e4f_f723
+        );
+    }
+
+    #[test]
+    fn test_sid_to_gidnumber() {
+        let idmap =
+            IdMap::new(IDMAP_RANGE_MIN, IDMAP_RANGE_MAX, IDMAP_RANGE_SIZE).expect("Invalid idmap");
+        let sid = Sid::from_bytes(DOMAIN_ADMINS_SID).expect("Invalid sid");
+
+        let gid = idmap.sid_to_gidnumber(&sid).expect("Failed to map sid");
+        // The gid is the rid within the slice of the domain.
+        assert_eq!((gid - IDMAP_RANGE_MIN) % IDMAP_RANGE_SIZE, 512);
+        assert!(gid < IDMAP_RANGE_MAX);
+
+        // Entries of the same domain share the slice.
+        let mut user = sid.clone();
+        user.sub_authorities.pop();
+        user.sub_authorities.push(1104);
+        assert_eq!(idmap.sid_to_gidnumber(&user), Some(gid - 512 + 1104));
+
+        assert!(IdMap::new(IDMAP_RANGE_MIN, IDMAP_RANGE_MIN, IDMAP_RANGE_SIZE).is_none());
+    }
+
+    #[test]
+    fn test_guid_from_bytes() {
+        let bytes = decode_binary("EjRWeJq83vAAEiM0VWZ3iA==").expect("Invalid base64");
+        assert_eq!(
+            guid_from_bytes(&bytes).map(|u| u.to_string()),
+            Some("78563412-bc9a-f0de-0012-233455667788".to_string())
+        );
+    }
+
+    #[test]
+    fn test_account_expires() {
+        assert_eq!(account_expires_to_rfc3339("0"), None);
+        assert_eq!(account_expires_to_rfc3339("9223372036854775807"), None);
+        assert_eq!(
+            account_expires_to_rfc3339("133801632000000000").as_deref(),
+            Some("2025-01-01T00:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn test_to_kanidm_name() {
+        assert_eq!(to_kanidm_name("JSmith"), "jsmith");
+        assert_eq!(to_kanidm_name("Domain Users"), "domain_users");
+        assert_eq!(to_kanidm_name("1stline"), "ad_1stline");
+        assert!(account_disabled("514"));
+        assert!(!account_disabled("512"));
+    }
+}
@@ -0,0 +1,869 @@
+#![deny(warnings)]
+#![warn(unused_extern_crates)]
+#![deny(clippy::todo)]
+#![deny(clippy::unimplemented)]
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::panic)]
+#![deny(clippy::unreachable)]
+#![deny(clippy::await_holding_lock)]
+#![deny(clippy::needless_pass_by_value)]
+#![deny(clippy::trivially_copy_pass_by_ref)]
+// We allow expect since it forces good error messages at the least.
+#![allow(clippy::expect_used)]
+
+mod config;
+mod error;
+mod idmap;
+
+use crate::config::{Config, EntryConfig, GidNumberSource};
+use crate::error::SyncError;
+use crate::idmap::{
+    account_disabled, account_expires_to_rfc3339, decode_binary, guid_from_bytes, to_kanidm_name,
+    IdMap, Sid,
+};
+use chrono::Utc;
+use clap::Parser;
+use cron::Schedule;
+use kanidm_proto::attribute::Attribute;
+use kanidm_proto::constants::ATTR_OBJECTCLASS;
+use serde::{Deserialize, Serialize};
+use std::fs::metadata;
+use std::fs::File;
+use std::io::Read;
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::runtime;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, EnvFilter};
+
+use kanidm_client::KanidmClientBuilder;
+use kanidm_lib_file_permissions::readonly as file_permissions_readonly;
+use kanidm_proto::scim_v1::{
+    MultiValueAttr, ScimEntry, ScimSshPubKey, ScimSyncGroup, ScimSyncPerson, ScimSyncRequest,
+    ScimSyncRetentionMode, ScimSyncState,
+};
+use uuid::Uuid;
+
+#[cfg(target_family = "unix")]
+use kanidm_utils_users::{get_current_gid, get_current_uid, get_effective_gid, get_effective_uid};
+
+use ldap3_client::{LdapClientBuilder, LdapEntry};
+
+include!("./opt.rs");
+
+// Attribute names are provided in lowercase by the ldap client.
+const AD_ATTR_OBJECTGUID: &str = "objectguid";
+const AD_ATTR_OBJECTSID: &str = "objectsid";
+const AD_ATTR_USNCHANGED: &str = "usnchanged";
+const AD_ATTR_USERACCOUNTCONTROL: &str = "useraccountcontrol";
+const AD_ATTR_ACCOUNTEXPIRES: &str = "accountexpires";
+
+const AD_CLASS_USER: &str = "user";
+const AD_CLASS_GROUP: &str = "group";
+
+async fn driver_main(opt: Opt) -> Result<(), ()> {
+    debug!("Starting kanidm ad sync driver.");
+
+    let mut f = match File::open(&opt.ad_sync_config) {
+        Ok(f) => f,
+        Err(e) => {
+            error!(
+                "Unable to open ad sync config from '{}' [{:?}] 🥺",
+                &opt.ad_sync_config.display(),
+                e
+            );
+            return Err(());
+        }
+    };
+
+    let mut contents = String::new();
+    if let Err(e) = f.read_to_string(&mut contents) {
+        error!(
+            "unable to read file '{}': {:?}",
+            &opt.ad_sync_config.display(),
+            e
+        );
+        return Err(());
+    };
+
+    let sync_config: Config = match toml::from_str(contents.as_str()) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!(
+                "Unable to parse config from '{}' error: {:?}",
+                &opt.ad_sync_config.display(),
+                e
+            );
+            return Err(());
+        }
+    };
+
+    debug!(?sync_config);
+
+    let cb = match KanidmClientBuilder::new().read_options_from_optional_config(&opt.client_config)
+    {
+        Ok(v) => v,
+        Err(_) => {
+            error!("Failed to parse {}", opt.client_config.to_string_lossy());
+            return Err(());
+        }
+    };
+
+    let expression = sync_config.schedule.as_deref().unwrap_or("0 */5 * * * * *");
+
+    let schedule = match Schedule::from_str(expression) {
+        Ok(s) => s,
+        Err(_) => {
+            error!("Failed to parse cron schedule expression");
+            return Err(());
+        }
+    };
+
+    if opt.schedule {
+        let last_op_status = Arc::new(AtomicBool::new(true));
+        let (broadcast_tx, mut broadcast_rx) = broadcast::channel(4);
+
+        let last_op_status_c = last_op_status.clone();
+
+        // Can we setup the socket for status?
+
+        let status_handle = if let Some(sb) = sync_config.status_bind.as_deref() {
+            // Can we bind?
+            let listener = match TcpListener::bind(sb).await {
+                Ok(l) => l,
+                Err(e) => {
+                    error!(?e, "Failed to bind status socket");
+                    return Err(());
+                }
+            };
+
+            info!("Status listener is started on {:?}", sb);
+            // Detach a status listener.
+            let status_rx = broadcast_tx.subscribe();
+            Some(tokio::spawn(async move {
+                status_task(listener, status_rx, last_op_status_c).await
+            }))
+        } else {
+            warn!("No status listener configured, this will prevent you monitoring the sync tool");
+            None
+        };
+
+        // main driver loop
+        let driver_handle = tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let next_time = match schedule.after(&now).next() {
+                    Some(v) => v,
+                    None => {
+                        error!("Failed to access any future scheduled events, terminating.");
+                        break;
+                    }
+                };
+
+                // If we don't do 1 + here we can trigger the event multiple times
+                // rapidly since we are in the same second.
+                let wait_seconds = 1 + (next_time - now).num_seconds() as u64;
+                info!("next sync on {}, wait_time = {}s", next_time, wait_seconds);
+
+                tokio::select! {
+                    _ = broadcast_rx.recv() => {
+                        // stop the event loop!
+                        break;
+                    }
+                    _ = sleep(Duration::from_secs(wait_seconds)) => {
+                        info!("starting sync ...");
+                        match run_sync(cb.clone(), &sync_config, &opt).await {
+                            Ok(_) => last_op_status.store(true, Ordering::Relaxed),
+                            Err(e) => {
+                                error!(?e, "sync completed with error");
+                                last_op_status.store(false, Ordering::Relaxed)
+                            }
+                        };
+                    }
+                }
+            }
+            info!("Stopped sync driver");
+        });
+
+        // TODO: this loop/handler should be generic across the various crates
+        // Block on signals now.
+        loop {
+            #[cfg(target_family = "unix")]
+            {
+                tokio::select! {
+                    Ok(()) = tokio::signal::ctrl_c() => {
+                        break
+                    }
+                    Some(()) = async move {
+                        let sigterm = tokio::signal::unix::SignalKind::terminate();
+                        #[allow(clippy::unwrap_used)]
+                        tokio::signal::unix::signal(sigterm).unwrap().recv().await
+                    } => {
+                        break
+                    }
+                    Some(()) = async move {
+                        let sigterm = tokio::signal::unix::SignalKind::alarm();
+                        #[allow(clippy::unwrap_used)]
+                        tokio::signal::unix::signal(sigterm).unwrap().recv().await
+                    } => {
+                        // Ignore
+                    }
+                    Some(()) = async move {
+                        let sigterm = tokio::signal::unix::SignalKind::hangup();
+                        #[allow(clippy::unwrap_used)]
+                        tokio::signal::unix::signal(sigterm).unwrap().recv().await
+                    } => {
+                        // Ignore
+                    }
+                    Some(()) = async move {
+                        let sigterm = tokio::signal::unix::SignalKind::user_defined1();
+                        #[allow(clippy::unwrap_used)]
+                        tokio::signal::unix::signal(sigterm).unwrap().recv().await
+                    } => {
+                        // Ignore
+                    }
+                    Some(()) = async move {
+                        let sigterm = tokio::signal::unix::SignalKind::user_defined2();
+                        #[allow(clippy::unwrap_used)]
+                        tokio::signal::unix::signal(sigterm).unwrap().recv().await
+                    } => {
+                        // Ignore
+                    }
+                }
+            }
+            #[cfg(target_family = "windows")]
+            {
+                tokio::select! {
+                    Ok(()) = tokio::signal::ctrl_c() => {
+                        break
+                    }
+                }
+            }
+        }
+
+        broadcast_tx
+            .send(true)
+            .expect("Failed to trigger a clean shutdown!");
+
+        let _ = driver_handle.await;
+        if let Some(sh) = status_handle {
+            let _ = sh.await;
+        }
+    } else if let Err(e) = run_sync(cb, &sync_config, &opt).await {
+        error!(?e, "Sync completed with error");
+    }
+    Ok(())
+}
+
+/// The position of the sync in the directory. Active Directory assigns each change a uSNChanged
+/// that increases on the domain controller that it is read from, so the cookie is only valid
+/// while we read from the same domain controller.
+#[derive(Debug, Serialize, Deserialize)]
+struct AdSyncCookie {
+    ad_uri: String,
+    highest_usn: u64,
+}
+
+async fn run_sync(
+    cb: KanidmClientBuilder,
+    sync_config: &Config,
+    opt: &Opt,
+) -> Result<(), SyncError> {
+    let rsclient = match cb.build() {
+        Ok(rsc) => rsc,
+        Err(_e) => {
+            error!("Failed to build async client");
+            return Err(SyncError::ClientConfig);
+        }
+    };
+
+    rsclient.set_token(sync_config.sync_token.clone()).await;
+
+    let Some(idmap) = IdMap::new(
+        sync_config.idmap_range_min,
+        sync_config.idmap_range_max,
+        sync_config.idmap_range_size,
+    ) else {
+        error!("Invalid idmap range - idmap_range_size must fit between idmap_range_min and idmap_range_max");
+        return Err(SyncError::Preprocess);
+    };
+
+    // Preflight check.
+    //  * can we connect to ldap?
+    let mut ldap_client = match LdapClientBuilder::new(&sync_config.ad_uri)
+        .max_ber_size(sync_config.max_ber_size)
+        .add_tls_ca(&sync_config.ad_ca)
+        .build()
+        .await
+    {
+        Ok(lc) => lc,
+        Err(e) => {
+            error!(?e, "Failed to connect to active directory");
+            return Err(SyncError::LdapConn);
+        }
+    };
+
+    match ldap_client
+        .bind(
+            sync_config.ad_sync_dn.clone(),
+            sync_config.ad_sync_pw.clone(),
+        )
+        .await
+    {
+        Ok(()) => {
+            debug!(ad_sync_dn = ?sync_config.ad_sync_dn, ad_uri = %sync_config.ad_uri);
+        }
+        Err(e) => {
+            error!(?e, "Failed to bind (authenticate) to active directory");
+            return Err(SyncError::LdapAuth);
+        }
+    };
+
+    //  * can we connect to kanidm?
+    // - get the current sync cookie from kanidm.
+    let scim_sync_status = match rsclient.scim_v1_sync_status().await {
+        Ok(s) => s,
+        Err(e) => {
+            error!(?e, "Failed to access scim sync status");
+            return Err(SyncError::SyncStatus);
+        }
+    };
+
+    debug!(state=?scim_sync_status);
+
+    // === Everything is connected! ===
+
+    let ad_uri = sync_config.ad_uri.to_string();
+
+    // If the cookie is from a different domain controller, then the usn is meaningless and
+    // we have to send every entry again.
+    let from_usn = match &scim_sync_status {
+        ScimSyncState::Refresh => None,
+        ScimSyncState::Active { cookie } => serde_json::from_slice::<AdSyncCookie>(cookie)
+            .ok()
+            .filter(|c| c.ad_uri == ad_uri)
+            .map(|c| c.highest_usn),
+    };
+
+    let filter = sync_config.ad_filter.clone();
+
+    // Every entry is searched, even if unchanged, since the set of present entries is how
+    // deletes are detected.
+    debug!(ad_sync_base_dn = ?sync_config.ad_sync_base_dn, ?from_usn, ?filter);
+    let ldap_entries = match ldap_client
+        .search(sync_config.ad_sync_base_dn.clone(), filter)
+        .await
+    {
+        Ok(result) => result.entries,
+        Err(e) => {
+            error!(?e, "Failed to search active directory");
+            return Err(SyncError::LdapSearch);
+        }
+    };
+
+    if opt.proto_dump {
+        let stdout = std::io::stdout();
+        if let Err(e) = serde_json::to_writer_pretty(stdout, &ldap_entries) {
+            error!(?e, "Failed to serialise ldap search response");
+        }
+    }
+
+    let (entries, present_uuids, highest_usn) =
+        match process_ad_entries(ldap_entries, from_usn, &idmap, sync_config) {
+            Ok(processed) => processed,
+            Err(()) => {
+                error!("Failed to process AD entries to SCIM");
+                return Err(SyncError::Preprocess);
+            }
+        };
+
+    let to_cookie = AdSyncCookie {
+        ad_uri,
+        highest_usn: highest_usn.max(from_usn.unwrap_or_default()),
+    };
+
+    let to_state = match serde_json::to_vec(&to_cookie) {
+        Ok(cookie) => ScimSyncState::Active { cookie },
+        Err(e) => {
+            error!(?e, "Failed to serialise sync cookie");
+            return Err(SyncError::Preprocess);
+        }
+    };
+
+    let scim_sync_request = ScimSyncRequest {
+        from_state: scim_sync_status,
+        to_state,
+        entries,
+        retain: ScimSyncRetentionMode::Retain(present_uuids),
+    };
+
+    if opt.proto_dump {
+        let stdout = std::io::stdout();
+        // write it out.
+        if let Err(e) = serde_json::to_writer_pretty(stdout, &scim_sync_request) {
+            error!(?e, "Failed to serialise scim sync request");
+        };
+        Ok(())
+    } else if opt.dry_run {
+        info!("dry-run complete");
+        info!("Success!");
+        Ok(())
+    } else if let Err(e) = rsclient.scim_v1_sync_update(&scim_sync_request).await {
+        error!(
+            ?e,
+            "Failed to submit scim sync update - see the kanidmd server log for more details."
+        );
+        Err(SyncError::SyncUpdate)
+    } else {
+        info!("Success!");
+        Ok(())
+    }
+    // done!
+}
+
+type ProcessedEntries = (Vec<ScimEntry>, Vec<Uuid>, u64);
+
+/// Convert the entries that changed since from_usn, and collect the ids of all entries that
+/// are still present so that kanidm can remove those that were deleted or moved out of scope.
+fn process_ad_entries(
+    ldap_entries: Vec<LdapEntry>,
+    from_usn: Option<u64>,
+    idmap: &IdMap,
+    sync_config: &Config,
+) -> Result<ProcessedEntries, ()> {
+    let mut entries = Vec::new();
+    let mut present_uuids = Vec::with_capacity(ldap_entries.len());
+    let mut highest_usn = 0;
+
+    for entry in ldap_entries {
+        let object_guid = entry
+            .get_ava_single(AD_ATTR_OBJECTGUID)
+            .and_then(decode_binary)
+            .and_then(|bytes| guid_from_bytes(&bytes))
+            .ok_or_else(|| {
+                error!("Invalid entry - missing or invalid objectGUID {}", entry.dn);
+            })?;
+
+        let usn_changed = entry
+            .get_ava_single(AD_ATTR_USNCHANGED)
+            .and_then(|usn| u64::from_str(usn).ok())
+            .unwrap_or_default();
+
+        let e_config = sync_config
+            .entry_map
+            .get(&object_guid)
+            .cloned()
+            .unwrap_or_default();
+
+        if e_config.exclude {
+            info!("entry_config excludes {}", entry.dn);
+            continue;
+        }
+
+        highest_usn = highest_usn.max(usn_changed);
+        present_uuids.push(e_config.map_uuid.unwrap_or(object_guid));
+
+        if from_usn.is_some_and(|from_usn| usn_changed <= from_usn) {
+            debug!("Unchanged entry {}", entry.dn);
+            continue;
+        }
+
+        if let Some(scim_entry) =
+            ad_to_scim_entry(entry, object_guid, &e_config, idmap, sync_config)?
+        {
+            entries.push(scim_entry);
+        }
+    }
+
+    Ok((entries, present_uuids, highest_usn))
+}
+
+fn ad_to_gidnumber(
+    entry: &LdapEntry,
+    rfc2307_attr: &str,
+    entry_config: &EntryConfig,
+    idmap: &IdMap,
+    sync_config: &Config,
+) -> Result<Option<u32>, ()> {
+    if let Some(number) = entry_config.map_gidnumber {
+        return Ok(Some(number));
+    }
+
+    match sync_config.gidnumber_source {
+        GidNumberSource::Sid => {
+            let sid = entry
+                .get_ava_single(AD_ATTR_OBJECTSID)
+                .and_then(decode_binary)
+                .and_then(|bytes| Sid::from_bytes(&bytes))
+                .ok_or_else(|| {
+                    error!("Invalid entry - missing or invalid objectSid {}", entry.dn);
+                })?;
+
+            idmap.sid_to_gidnumber(&sid).map(Some).ok_or_else(|| {
+                error!(
+                    "Unable to map {} to a gidnumber - the rid is larger than idmap_range_size",
+                    sid
+                );
+            })
+        }
+        GidNumberSource::Rfc2307 => entry
+            .get_ava_single(rfc2307_attr)
+            .map(|gid| {
+                u32::from_str(gid).map_err(|_| {
+                    error!("Invalid gidnumber - {} is not a u32", rfc2307_attr);
+                })
+            })
+            .transpose(),
+        GidNumberSource::None => Ok(None),
+    }
+}
+
+fn ad_to_scim_entry(
+    mut entry: LdapEntry,
+    object_guid: Uuid,
+    entry_config: &EntryConfig,
+    idmap: &IdMap,
+    sync_config: &Config,
+) -> Result<Option<ScimEntry>, ()> {
+    debug!("{:#?}", entry);
+
+    let oc = entry.attrs.get(ATTR_OBJECTCLASS).ok_or_else(|| {
+        error!("Invalid entry - no object class {}", entry.dn);
+    })?;
+
+    let id = entry_config.map_uuid.unwrap_or(object_guid);
+
+    // Computers are users too, so groups are checked first.
+    if oc.contains(AD_CLASS_GROUP) {
+        let name = if let Some(name) = entry_config.map_name.clone() {
+            name
+        } else {
+            entry
+                .get_ava_single(&sync_config.group_attr_name)
+                .map(to_kanidm_name)
+                .ok_or_else(|| {
+                    error!(
+                        "Missing required attribute {} (group_attr_name)",
+                        sync_config.group_attr_name
+                    );
+                })?
+        };
+
+        let description = entry
+            .get_ava_single(&sync_config.group_attr_description)
+            .map(str::to_string);
+
+        let gidnumber = ad_to_gidnumber(
+            &entry,
+            Attribute::GidNumber.as_ref(),
+            entry_config,
+            idmap,
+            sync_config,
+        )?;
+
+        // Members are referenced by their dn, which is the external id of the synced entry.
+        let members: Vec<_> = entry
+            .remove_ava(Attribute::Member.as_ref())
+            .map(|set| set.into_iter().collect())
+            .unwrap_or_default();
+
+        let scim_sync_group = ScimSyncGroup::builder(id, entry.dn, name)
+            .set_description(description)
+            .set_gidnumber(gidnumber)
+            .set_members(members.into_iter())
+            .build();
+
+        let scim_entry_generic: ScimEntry = scim_sync_group.try_into().map_err(|json_err| {
+            error!(?json_err, "Unable to convert group to scim_sync_group");
+        })?;
+
+        Ok(Some(scim_entry_generic))
+    } else if oc.contains(AD_CLASS_USER) {
+        let user_name = if let Some(name) = entry_config.map_name.clone() {
+            name
+        } else {
+            entry
+                .get_ava_single(&sync_config.person_attr_user_name)
+                .map(to_kanidm_name)
+                .ok_or_else(|| {
+                    error!(
+                        "Missing required attribute {} (person_attr_user_name)",
+                        sync_config.person_attr_user_name
+                    );
+                })?
+        };
+
+        // Many accounts in AD have no displayName, so fall back to the cn and then the name.
+        let display_name = entry
+            .get_ava_single(&sync_config.person_attr_display_name)
+            .or_else(|| entry.get_ava_single(Attribute::Cn.as_ref()))
+            .map(str::to_string)
+            .unwrap_or_else(|| user_name.clone());
+
+        let gidnumber = ad_to_gidnumber(
+            &entry,
+            Attribute::UidNumber.as_ref(),
+            entry_config,
+            idmap,
+            sync_config,
+        )?;
+
+        let mail: Vec<_> = entry
+            .remove_ava(&sync_config.person_attr_mail)
+            .map(|set| {
+                set.into_iter()
+                    .map(|addr| MultiValueAttr {
+                        type_: None,
+                        primary: None,
+                        display: None,
+                        ref_: None,
+                        value: addr,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let ssh_publickey = entry
+            .remove_ava(&sync_config.person_attr_ssh_public_key)
+            .map(|set| {
+                set.into_iter()
+                    .enumerate()
+                    .map(|(i, value)| ScimSshPubKey {
+                        label: format!("sshpublickey-{}", i),
+                        value,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let account_disabled = entry
+            .get_ava_single(AD_ATTR_USERACCOUNTCONTROL)
+            .map(account_disabled)
+            .unwrap_or_default();
+
+        // A disabled account is expired from the epoch.
+        let account_expire = if account_disabled {
+            Some(chrono::DateTime::UNIX_EPOCH.to_rfc3339())
+        } else {
+            entry
+                .get_ava_single(AD_ATTR_ACCOUNTEXPIRES)
+                .and_then(account_expires_to_rfc3339)
+        };
+
+        let login_shell = entry
+            .get_ava_single(&sync_config.person_attr_login_shell)
+            .map(str::to_string);
+
+        // Active Directory never discloses password hashes, so people must set their
+        // credentials in kanidm.
+        let scim_sync_person = ScimSyncPerson::builder(id, entry.dn, user_name, display_name)
+            .set_gidnumber(gidnumber)
+            .set_login_shell(login_shell)
+            .set_mail(mail)
+            .set_ssh_publickey(ssh_publickey)
+            .set_account_expire(account_expire)
+            .build();
+
+        let scim_entry_generic: ScimEntry = scim_sync_person.try_into().map_err(|json_err| {
+            error!(?json_err, "Unable to convert person to scim_sync_person");
+        })?;
+
+        Ok(Some(scim_entry_generic))
+    } else {
+        debug!("Skipping entry {} with oc {:?}", entry.dn, oc);
+        Ok(None)
+    }
+}
+
+async fn status_task(
+    listener: TcpListener,
+    mut status_rx: broadcast::Receiver<bool>,
+    last_op_status: Arc<AtomicBool>,
+) {
+    loop {
+        tokio::select! {
+            _ = status_rx.recv() => {
+                break;
+            }
+            maybe_sock = listener.accept() => {
+                let mut stream = match maybe_sock {
+                    Ok((sock, addr)) => {
+                        debug!("accept from {:?}", addr);
+                        sock
+                    }
+                    Err(e) => {
+                        error!(?e, "Failed to accept status connection");
+                        continue;
+                    }
+                };
+
+                let sr = if last_op_status.load(Ordering::Relaxed) {
+                     stream.write_all(b"Ok\n").await
+                } else {
+                     stream.write_all(b"Err\n").await
+                };
+                if let Err(e) = sr {
+                    error!(?e, "Failed to send status");
+                }
+            }
+        }
+    }
+    info!("Stopped status task");
+}
+
+fn config_security_checks(cfg_path: &Path) -> bool {
+    let cfg_path_str = cfg_path.to_string_lossy();
+
+    if !cfg_path.exists() {
+        // there's no point trying to start up if we can't read a usable config!
+        error!(
+            "Config missing from {} - cannot start up. Quitting.",
+            cfg_path_str
+        );
+        false
+    } else {
+        let cfg_meta = match metadata(cfg_path) {
+            Ok(v) => v,
+            Err(e) => {
+                error!(
+                    "Unable to read metadata for '{}' during security checks - {:?}",
+                    cfg_path_str, e
+                );
+                return false;
+            }
+        };
+        if !file_permissions_readonly(&cfg_meta) {
+            warn!("permissions on {} may not be secure. Should be readonly to running uid. This could be a security risk ...",
+                cfg_path_str
+                );
+        }
+
+        #[cfg(target_family = "unix")]
+        if cfg_meta.uid() == get_current_uid() || cfg_meta.uid() == get_effective_uid() {
+            warn!("WARNING: {} owned by the current uid, which may allow file permission changes. This could be a security risk ...",
+                cfg_path_str
+            );
+        }
+
+        true
+    }
+}
+
+fn main() {
+    let opt = Opt::parse();
+
+    let fmt_layer = fmt::layer().with_writer(std::io::stderr);
+
+    let filter_layer = if opt.debug {
+        match EnvFilter::try_new("kanidm_client=debug,kanidm_ad_sync=debug,ldap3_client=debug") {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("ERROR! Unable to start tracing {:?}", e);
+                return;
+            }
+        }
+    } else {
+        match EnvFilter::try_from_default_env() {
+            Ok(f) => f,
+            Err(_) => EnvFilter::new("kanidm_client=warn,kanidm_ad_sync=info,ldap3_client=warn"),
+        }
+    };
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(fmt_layer)
+        .init();
+
+    // Startup sanity checks.
+    // TODO: put this in the junk drawer
+    #[cfg(target_family = "unix")]
+    if opt.skip_root_check {
+        warn!("Skipping root user check, if you're running this for testing, ensure you clean up temporary files.")
+    } else if get_current_uid() == 0
+        || get_effective_uid() == 0
+        || get_current_gid() == 0
+        || get_effective_gid() == 0
+    {
+        error!("Refusing to run - this process must not operate as root.");
+        return;
+    };
+
+    if !config_security_checks(&opt.client_config) || !config_security_checks(&opt.ad_sync_config) {
+        return;
+    }
+
+    let par_count = thread::available_parallelism()
+        .expect("Failed to determine available parallelism")
+        .get();
+
+    let rt = runtime::Builder::new_current_thread()
+        // We configure this as we use parallel workers at some points.
+        .max_blocking_threads(par_count)
+        .enable_all()
+        .build()
+        .expect("Failed to initialise tokio runtime!");
+
+    #[cfg(debug_assertions)]
+    tracing::debug!("Using {} worker threads", par_count);
+
+    if rt.block_on(async move { driver_main(opt).await }).is_err() {
+        std::process::exit(1);
+    };
+}
+
+#[tokio::test]
+async fn test_driver_main() {
+    let testopt = Opt {
+        client_config: PathBuf::from("test"),
+        ad_sync_config: PathBuf::from("test"),
+        debug: false,
+        schedule: false,
+        proto_dump: false,
+        dry_run: false,
+        skip_root_check: true,
+    };
+    sketching::test_init();
+
+    println!("testing config");
+    // because it can't find the profile file it'll just stop
+    assert!(driver_main(testopt.clone()).await.is_err());
+    println!("done testing missing config");
+
+    let testopt = Opt {
+        client_config: PathBuf::from(format!("{}/Cargo.toml", env!("CARGO_MANIFEST_DIR"))),
+        ad_sync_config: PathBuf::from(format!("{}/Cargo.toml", env!("CARGO_MANIFEST_DIR"))),
+        ..testopt
+    };
+    println!("valid file path, invalid contents");
+    assert!(driver_main(testopt.clone()).await.is_err());
+    println!("done with valid file path, invalid contents");
+
+    let testopt = Opt {
+        client_config: PathBuf::from(format!(
+            "{}/../../../examples/iam_migration_ad.toml",
+            env!("CARGO_MANIFEST_DIR")
+        )),
+        ad_sync_config: PathBuf::from(format!(
+            "{}/../../../examples/iam_migration_ad.toml",
+            env!("CARGO_MANIFEST_DIR")
+        )),
+        ..testopt
+    };
+
+    println!("valid file path, invalid contents");
+    assert!(driver_main(testopt).await.is_err());
+    println!("done with valid file path, valid contents");
+}
@@ -2,7 +2,8 @@ use crate::error::Error;
 use crate::kani::KanidmOrcaClient;
 use crate::model::ActorRole;
 use crate::profile::Profile;
-use crate::state::{Credential, Flag, Group, GroupName, Person, PreflightState, State};
+use crate::scenario::Scenario;
+use crate::state::{Credential, Flag, Group, GroupName, Model, Person, PreflightState, State};
 use hashbrown::HashMap;
 use rand::distributions::{Alphanumeric, DistString, Uniform};
 use rand::seq::{index, SliceRandom};
@@ -53,8 +54,18 @@ pub async fn populate(_client: &KanidmOrcaClient, profile: Profile) -> Result<St
 
     let thread_count = profile.thread_count();
 
+    // The mixed model needs to know which credentials to enrol.
+    let scenario = match (profile.model(), profile.scenario()) {
+        (Model::Mixed, Some(path)) => Some(Scenario::try_from(path)?),
+        (Model::Mixed, None) => {
+            error!("The mixed model requires a scenario file to be set in the profile");
+            return Err(Error::InvalidState);
+        }
+        _ => None,
+    };
+
     // PHASE 0 - For now, set require MFA off and extend the privilege expiry.
-    let preflight_flags = vec![
+    let mut preflight_flags = vec![
         Flag::DisableAllPersonsMFAPolicy,
         Flag::ExtendPrivilegedAuthExpiry,
     ];
@@ -141,6 +152,7 @@ pub async fn populate(_client: &KanidmOrcaClient, profile: Profile) -> Result<St
             roles,
             credential: Credential::Password { plain: password },
             model,
+            posix: false,
         };
 
         debug!(?p);
@@ -198,6 +210,23 @@ pub async fn populate(_client: &KanidmOrcaClient, profile: Profile) -> Result<St
         }
     }
 
+    // Give persons the credentials the scenario needs. This happens after the roles are
+    // assigned so that the other models generate the same state as before.
+    if let Some(scenario) = &scenario {
+        if scenario.requires_ldap() {
+            preflight_flags.push(Flag::AllowLdapUnixPasswordBind);
+        }
+
+        for person in persons.iter_mut() {
+            person.posix = scenario.requires_ldap();
+
+            if seeded_rng.gen_range(0..100) < scenario.totp_percent() {
+                let plain = person.credential.password().to_string();
+                person.credential = Credential::PasswordTotp { plain, totp: None };
+            }
+        }
+    }
+
     // PHASE 4 - generate groups for user modification rights
 
     // PHASE 5 - generate excess groups with nesting. Randomly assign persons.
@@ -217,6 +246,7 @@ pub async fn populate(_client: &KanidmOrcaClient, profile: Profile) -> Result<St
         preflight_flags,
         persons,
         thread_count,
+        scenario,
     };
 
     Ok(state)
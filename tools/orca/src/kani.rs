@@ -1,13 +1,14 @@
 use kanidm_client::{KanidmClient, KanidmClientBuilder};
+use kanidm_proto::internal::{CURegState, TotpSecret};
 
 use crate::error::Error;
 use crate::profile::Profile;
+use crate::totp;
 
 // This client contains our admin and idm_admin connections that are
 // pre-authenticated for use against the kanidm server. In addition,
 // new clients can be requested for our test actors.
 pub struct KanidmOrcaClient {
-    #[allow(dead_code)]
     admin_client: KanidmClient,
     idm_admin_client: KanidmClient,
     // In future we probably need a way to connect to all the nodes?
@@ -82,6 +83,16 @@ impl KanidmOrcaClient {
             })
     }
 
+    pub async fn allow_ldap_unix_password_bind(&self) -> Result<(), Error> {
+        self.admin_client
+            .idm_set_ldap_allow_unix_password_bind(true)
+            .await
+            .map_err(|err| {
+                error!(?err, "Unable to allow ldap unix password binds");
+                Error::KanidmClient
+            })
+    }
+
     pub async fn person_exists(&self, username: &str) -> Result<bool, Error> {
         self.idm_admin_client
             .idm_person_account_get(username)
@@ -117,6 +128,93 @@ impl KanidmOrcaClient {
             })
     }
 
+    /// Set the password and enrol a TOTP for a person, returning the secret so that the
+    /// person can sign in with it during the test.
+    pub async fn person_set_primary_password_totp(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<TotpSecret, Error> {
+        let (session_token, _status) = self
+            .idm_admin_client
+            .idm_account_credential_update_begin(username)
+            .await
+            .map_err(|err| {
+                error!(?err, ?username, "Unable to begin credential update");
+                Error::KanidmClient
+            })?;
+
+        self.idm_admin_client
+            .idm_account_credential_update_set_password(&session_token, password)
+            .await
+            .map_err(|err| {
+                error!(?err, ?username, "Unable to set person password");
+                Error::KanidmClient
+            })?;
+
+        let status = self
+            .idm_admin_client
+            .idm_account_credential_update_init_totp(&session_token)
+            .await
+            .map_err(|err| {
+                error!(?err, ?username, "Unable to generate person totp");
+                Error::KanidmClient
+            })?;
+
+        let CURegState::TotpCheck(secret) = status.mfaregstate else {
+            error!(?username, "Server did not provide a totp secret");
+            return Err(Error::KanidmClient);
+        };
+
+        let totp = totp::totp_now(&secret)?;
+
+        let status = self
+            .idm_admin_client
+            .idm_account_credential_update_check_totp(&session_token, totp, "orca")
+            .await
+            .map_err(|err| {
+                error!(?err, ?username, "Unable to verify person totp");
+                Error::KanidmClient
+            })?;
+
+        if !matches!(status.mfaregstate, CURegState::None) {
+            error!(?username, mfaregstate = ?status.mfaregstate, "Person totp was not accepted");
+            return Err(Error::KanidmClient);
+        }
+
+        self.idm_admin_client
+            .idm_account_credential_update_commit(&session_token)
+            .await
+            .map_err(|err| {
+                error!(?err, ?username, "Unable to commit person credentials");
+                Error::KanidmClient
+            })?;
+
+        Ok(secret)
+    }
+
+    pub async fn person_set_unix_password(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<(), Error> {
+        self.idm_admin_client
+            .idm_person_account_unix_extend(username, None, None)
+            .await
+            .map_err(|err| {
+                error!(?err, ?username, "Unable to extend person with posix");
+                Error::KanidmClient
+            })?;
+
+        self.idm_admin_client
+            .idm_person_account_unix_cred_put(username, password)
+            .await
+            .map_err(|err| {
+                error!(?err, ?username, "Unable to set person unix password");
+                Error::KanidmClient
+            })
+    }
+
     pub async fn group_set_members(&self, group_name: &str, members: &[&str]) -> Result<(), Error> {
         self.idm_admin_client
             .idm_group_set_members(group_name, members)
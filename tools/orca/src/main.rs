@@ -31,8 +31,10 @@ mod opt;
 mod populate;
 mod profile;
 mod run;
+mod scenario;
 mod state;
 mod stats;
+mod totp;
 
 impl OrcaOpt {
     fn debug(&self) -> bool {
@@ -78,6 +80,9 @@ fn main() -> ExitCode {
             threads,
             model,
             dump_raw_data,
+            scenario,
+            ldap_uri,
+            ldap_ca,
         } => {
             // For now I hardcoded some dimensions, but we should prompt
             // the user for these later.
@@ -101,7 +106,10 @@ fn main() -> ExitCode {
                 threads,
                 dump_raw_data,
             )
-            .seed(seed);
+            .seed(seed)
+            .scenario(scenario)
+            .ldap_uri(ldap_uri)
+            .ldap_ca(ldap_ca);
 
             let profile = match builder.build() {
                 Ok(p) => p,
@@ -197,12 +205,18 @@ fn main() -> ExitCode {
             // here we want all threads available to speed up the process.
             let runtime = build_tokio_runtime(state.thread_count);
 
-            runtime.block_on(async {
+            let state = runtime.block_on(async {
                 match populate::preflight(state).await {
-                    Ok(_) => ExitCode::SUCCESS,
-                    Err(_err) => ExitCode::FAILURE,
+                    Ok(state) => Some(state),
+                    Err(_err) => None,
                 }
-            })
+            });
+
+            // Save any credentials that were enrolled during preflight.
+            match state.map(|state| state.write_to_path(&state_path)) {
+                Some(Ok(_)) => ExitCode::SUCCESS,
+                _ => ExitCode::FAILURE,
+            }
         }
 
         // Run the test based on the state file.
@@ -1,9 +1,13 @@
 use crate::error::Error;
 use crate::run::{EventDetail, EventRecord};
 use crate::state::*;
+use crate::totp;
+use std::fmt::Debug;
 use std::time::{Duration, Instant};
 
-use kanidm_client::{ClientError, KanidmClient};
+use kanidm_client::KanidmClient;
+use ldap3_client::LdapClientBuilder;
+use url::Url;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -83,20 +87,30 @@ pub async fn login(
 ) -> Result<(TransitionResult, Vec<EventRecord>), Error> {
     // Should we measure the time of each call rather than the time with multiple calls?
     let start = Instant::now();
-    let result = match &person.credential {
-        Credential::Password { plain } => {
+    let (result, details) = match &person.credential {
+        Credential::Password { plain } | Credential::PasswordTotp { plain, totp: None } => (
             client
                 .auth_simple_password(person.username.as_str(), plain.as_str())
-                .await
+                .await,
+            EventDetail::Login,
+        ),
+        Credential::PasswordTotp {
+            plain,
+            totp: Some(secret),
+        } => {
+            let totp = totp::totp_now(secret)?;
+            (
+                client
+                    .auth_password_totp(person.username.as_str(), plain.as_str(), totp)
+                    .await,
+                EventDetail::LoginTotp,
+            )
         }
     };
 
     let duration = Instant::now().duration_since(start);
     Ok(parse_call_result_into_transition_result_and_event_record(
-        result,
-        EventDetail::Login,
-        start,
-        duration,
+        result, details, start, duration,
     ))
 }
 
@@ -194,7 +208,16 @@ pub async fn privilege_reauth(
     let start = Instant::now();
 
     let result = match &person.credential {
-        Credential::Password { plain } => client.reauth_simple_password(plain.as_str()).await,
+        Credential::Password { plain } | Credential::PasswordTotp { plain, totp: None } => {
+            client.reauth_simple_password(plain.as_str()).await
+        }
+        Credential::PasswordTotp {
+            plain,
+            totp: Some(secret),
+        } => {
+            let totp = totp::totp_now(secret)?;
+            client.reauth_password_totp(plain.as_str(), totp).await
+        }
     };
 
     let duration = Instant::now().duration_since(start);
@@ -256,8 +279,64 @@ pub async fn person_get_self_memberof(
     ))
 }
 
-fn parse_call_result_into_transition_result_and_event_record<T>(
-    result: Result<T, ClientError>,
+pub async fn person_search(
+    client: &KanidmClient,
+    person: &Person,
+) -> Result<(TransitionResult, Vec<EventRecord>), Error> {
+    // Search with the start of the person's name, as someone looking up a colleague would.
+    let term = person.username.chars().take(3).collect::<String>();
+
+    let start = Instant::now();
+    let result = client.idm_person_search(&term).await;
+    let duration = Instant::now().duration_since(start);
+    Ok(parse_call_result_into_transition_result_and_event_record(
+        result,
+        EventDetail::PersonSearch,
+        start,
+        duration,
+    ))
+}
+
+pub async fn ldap_bind(
+    ldap_url: &Url,
+    ldap_ca: Option<&String>,
+    person: &Person,
+) -> Result<(TransitionResult, Vec<EventRecord>), Error> {
+    // Most LDAP clients connect for each bind, so we measure the connection setup as well.
+    let start = Instant::now();
+    let result = async {
+        let mut builder = LdapClientBuilder::new(ldap_url);
+        if let Some(ldap_ca) = ldap_ca {
+            builder = builder.add_tls_ca(ldap_ca);
+        }
+
+        let mut ldap_client = builder.build().await.map_err(|err| {
+            debug!(?err, "Unable to connect to ldap");
+        })?;
+
+        ldap_client
+            .bind(
+                person.username.clone(),
+                person.credential.password().to_string(),
+            )
+            .await
+            .map_err(|err| {
+                debug!(?err, "Unable to bind to ldap");
+            })
+    }
+    .await;
+
+    let duration = Instant::now().duration_since(start);
+    Ok(parse_call_result_into_transition_result_and_event_record(
+        result,
+        EventDetail::LdapBind,
+        start,
+        duration,
+    ))
+}
+
+fn parse_call_result_into_transition_result_and_event_record<T, E: Debug>(
+    result: Result<T, E>,
     details: EventDetail,
     start: Instant,
     duration: Duration,
@@ -66,7 +66,7 @@ impl ActorModel for ActorBasic {
             }
             TransitionAction::WriteSelfPassword => {
                 // I know it's dumb but here we just re-set the same password because it's the simplest thing to do
-                let Credential::Password { plain } = &person.credential;
+                let plain = person.credential.password();
                 model::person_set_self_password(client, person, plain).await
             }
         }?;
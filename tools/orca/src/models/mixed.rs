@@ -0,0 +1,154 @@
+use crate::model::{self, ActorModel, TransitionResult};
+
+use crate::error::Error;
+use crate::run::EventRecord;
+use crate::scenario::{Scenario, ScenarioAction};
+use crate::state::*;
+use kanidm_client::KanidmClient;
+
+use async_trait::async_trait;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use url::Url;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What the mixed model needs at run time that isn't part of a person.
+pub struct MixedContext {
+    pub scenario: Scenario,
+    pub ldap_url: Option<Url>,
+    pub ldap_ca: Option<String>,
+}
+
+enum State {
+    Unauthenticated,
+    Authenticated,
+}
+
+struct Transition {
+    delay: Duration,
+    action: ScenarioAction,
+}
+
+pub struct ActorMixed {
+    state: State,
+    randomised_backoff_time: Duration,
+    cha_rng: ChaCha8Rng,
+    actions: Vec<ScenarioAction>,
+    weights: WeightedIndex<u64>,
+    context: Arc<MixedContext>,
+}
+
+impl ActorMixed {
+    pub fn new(
+        rng_seed: u64,
+        person_name: &str,
+        warmup_time_ms: u64,
+        context: Arc<MixedContext>,
+    ) -> Result<Self, Error> {
+        // Every actor shares the same seed, so mix in the person's name to stop them all
+        // choosing the same action at the same time.
+        let rng_seed = person_name
+            .bytes()
+            .fold(rng_seed, |acc, b| acc.rotate_left(5) ^ u64::from(b));
+        let mut cha_rng = ChaCha8Rng::seed_from_u64(rng_seed);
+
+        let max_backoff_time_in_ms = 2 * warmup_time_ms / 3;
+        let randomised_backoff_time =
+            Duration::from_millis(cha_rng.gen_range(0..max_backoff_time_in_ms));
+
+        let (actions, weights): (Vec<_>, Vec<_>) = context.scenario.weights().unzip();
+        let weights = WeightedIndex::new(weights).map_err(|err| {
+            error!(?err, "Invalid scenario weights");
+            Error::InvalidState
+        })?;
+
+        Ok(ActorMixed {
+            state: State::Unauthenticated,
+            randomised_backoff_time,
+            cha_rng,
+            actions,
+            weights,
+            context,
+        })
+    }
+}
+
+#[async_trait]
+impl ActorModel for ActorMixed {
+    async fn transition(
+        &mut self,
+        client: &KanidmClient,
+        person: &Person,
+    ) -> Result<Vec<EventRecord>, Error> {
+        let transition = self.next_transition();
+
+        tokio::time::sleep(transition.delay).await;
+
+        let (result, event) = match transition.action {
+            ScenarioAction::Login => model::login(client, person).await,
+            ScenarioAction::SessionRefresh => model::privilege_reauth(client, person).await,
+            ScenarioAction::LdapBind => {
+                let Some(ldap_url) = self.context.ldap_url.as_ref() else {
+                    error!("The scenario includes LDAP binds, but no ldap uri is set");
+                    return Err(Error::Ldap);
+                };
+                model::ldap_bind(ldap_url, self.context.ldap_ca.as_ref(), person).await
+            }
+            ScenarioAction::Search => model::person_search(client, person).await,
+            ScenarioAction::ReadSelfAccount => model::person_get_self_account(client, person).await,
+            ScenarioAction::ReadSelfMemberOf => {
+                model::person_get_self_memberof(client, person).await
+            }
+        }?;
+
+        self.next_state(transition.action, result);
+
+        Ok(event)
+    }
+}
+
+impl ActorMixed {
+    fn next_transition(&mut self) -> Transition {
+        match self.state {
+            State::Unauthenticated => Transition {
+                delay: self.randomised_backoff_time,
+                action: ScenarioAction::Login,
+            },
+            State::Authenticated => {
+                // Vary the think time by half either way so that actors drift apart.
+                let think_time_ms = self.context.scenario.think_time().as_millis() as u64;
+                let delay = Duration::from_millis(
+                    self.cha_rng
+                        .gen_range(think_time_ms / 2..=think_time_ms + think_time_ms / 2),
+                );
+
+                let action = self
+                    .actions
+                    .get(self.weights.sample(&mut self.cha_rng))
+                    .copied()
+                    .unwrap_or(ScenarioAction::Login);
+
+                Transition { delay, action }
+            }
+        }
+    }
+
+    fn next_state(&mut self, action: ScenarioAction, result: TransitionResult) {
+        match (&self.state, action, result) {
+            (_, ScenarioAction::Login, TransitionResult::Ok)
+            | (State::Authenticated, _, TransitionResult::Ok) => {
+                self.state = State::Authenticated;
+            }
+            #[allow(clippy::unreachable)]
+            (State::Unauthenticated, _, TransitionResult::Ok) => {
+                unreachable!();
+            }
+            (_, _, TransitionResult::Error) => {
+                self.state = State::Unauthenticated;
+            }
+        }
+    }
+}
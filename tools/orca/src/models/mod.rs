@@ -2,5 +2,6 @@ pub(crate) mod auth_only;
 pub(crate) mod basic;
 // pub(crate) mod markov;
 pub(crate) mod latency_measurer;
+pub(crate) mod mixed;
 pub(crate) mod read;
 pub(crate) mod write;
@@ -74,6 +74,18 @@ pub enum OrcaOpt {
         #[clap(long, default_value_t)]
         /// Dump raw data to a separate csv file, defaults to false
         dump_raw_data: bool,
+
+        #[clap(long)]
+        /// The scenario file describing the workload of the `mixed` model
+        scenario: Option<PathBuf>,
+
+        #[clap(long)]
+        /// The LDAP URI to bind to, required if the scenario includes LDAP binds
+        ldap_uri: Option<String>,
+
+        #[clap(long)]
+        /// The CA certificate to verify the LDAP server with
+        ldap_ca: Option<String>,
     },
 
     #[clap(name = "conntest")]
@@ -13,6 +13,7 @@ async fn apply_flags(client: Arc<kani::KanidmOrcaClient>, flags: &[Flag]) -> Res
         match flag {
             Flag::DisableAllPersonsMFAPolicy => client.disable_mfa_requirement().await?,
             Flag::ExtendPrivilegedAuthExpiry => client.extend_privilege_expiry().await?,
+            Flag::AllowLdapUnixPasswordBind => client.allow_ldap_unix_password_bind().await?,
         }
     }
     Ok(())
@@ -20,7 +21,7 @@ async fn apply_flags(client: Arc<kani::KanidmOrcaClient>, flags: &[Flag]) -> Res
 
 async fn preflight_person(
     client: Arc<kani::KanidmOrcaClient>,
-    person: Person,
+    person: &mut Person,
 ) -> Result<(), Error> {
     debug!(?person);
 
@@ -33,12 +34,24 @@ async fn preflight_person(
             .await?;
     }
 
-    match &person.credential {
+    match &mut person.credential {
         Credential::Password { plain } => {
             client
                 .person_set_primary_password_only(&person.username, plain)
                 .await?;
         }
+        Credential::PasswordTotp { plain, totp } => {
+            let secret = client
+                .person_set_primary_password_totp(&person.username, plain)
+                .await?;
+            *totp = Some(secret);
+        }
+    }
+
+    if person.posix {
+        client
+            .person_set_unix_password(&person.username, person.credential.password())
+            .await?;
     }
 
     // For each role we are part of, did we have other permissions required to fulfil that?
@@ -55,7 +68,7 @@ async fn preflight_person(
     Ok(())
 }
 
-async fn preflight_group(client: Arc<kani::KanidmOrcaClient>, group: Group) -> Result<(), Error> {
+async fn preflight_group(client: Arc<kani::KanidmOrcaClient>, group: &Group) -> Result<(), Error> {
     if client.group_exists(&group.name.to_string()).await? {
         // Do nothing? Do we need to reset them later?
     } else {
@@ -73,7 +86,9 @@ async fn preflight_group(client: Arc<kani::KanidmOrcaClient>, group: Group) -> R
     Ok(())
 }
 
-pub async fn preflight(state: State) -> Result<(), Error> {
+/// Create the entries of the state in the Kanidm instance. The returned state includes the
+/// TOTP secrets that were enrolled, so it must be saved to be able to run the test.
+pub async fn preflight(mut state: State) -> Result<State, Error> {
     // Get the admin client.
     let client = Arc::new(kani::KanidmOrcaClient::new(&state.profile).await?);
 
@@ -84,21 +99,26 @@ pub async fn preflight(state: State) -> Result<(), Error> {
     let mut tasks = VecDeque::with_capacity(state_persons_len);
 
     // Create persons.
-    for person in state.persons.into_iter() {
+    for (idx, mut person) in std::mem::take(&mut state.persons).into_iter().enumerate() {
         let c = client.clone();
         // While writes are single threaded in Kanidm, searches (such as .exists)
         // and credential updates are concurrent / parallel. So these parts can be
         // called in parallel, so we divide up into workers.
-        tasks.push_back(preflight_person(c, person))
+        tasks.push_back(async move {
+            let _ = preflight_person(c, &mut person).await;
+            (idx, person)
+        })
     }
 
     let tasks = Arc::new(Mutex::new(tasks));
+    let persons = Arc::new(Mutex::new(Vec::with_capacity(state_persons_len)));
     let counter = Arc::new(AtomicU32::new(0));
     let par = std::thread::available_parallelism().unwrap();
 
     let handles: Vec<_> = (0..par.into())
         .map(|_| {
             let tasks_q = tasks.clone();
+            let persons_c = persons.clone();
             let counter_c = counter.clone();
             tokio::spawn(async move {
                 loop {
@@ -109,7 +129,8 @@ pub async fn preflight(state: State) -> Result<(), Error> {
                     .await;
 
                     if let Some(t) = maybe_task {
-                        let _ = t.await;
+                        let person = t.await;
+                        persons_c.lock().await.push(person);
                         let was = counter_c.fetch_add(1, Ordering::Relaxed);
                         if was % 1000 == 999 {
                             let order = was + 1;
@@ -136,11 +157,16 @@ pub async fn preflight(state: State) -> Result<(), Error> {
 
     eprintln!("done");
 
+    // Restore the original order of persons, so that the test remains deterministic.
+    let mut persons = std::mem::take(&mut *persons.lock().await);
+    persons.sort_unstable_by_key(|(idx, _)| *idx);
+    state.persons = persons.into_iter().map(|(_, person)| person).collect();
+
     // Create groups.
     let counter = Arc::new(AtomicU32::new(0));
     let mut tasks = Vec::with_capacity(state.groups.len());
 
-    for group in state.groups.into_iter() {
+    for group in state.groups.iter() {
         let c = client.clone();
         // Write operations are single threaded in Kanidm, so we don't need to attempt
         // to parallelise that here.
@@ -165,5 +191,5 @@ pub async fn preflight(state: State) -> Result<(), Error> {
     // Create integrations.
 
     info!("Ready to 🛫");
-    Ok(())
+    Ok(state)
 }
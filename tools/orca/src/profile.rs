@@ -4,7 +4,7 @@ use rand::{thread_rng, Rng};
 use serde::de::{value, IntoDeserializer};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 // Sorry nerds, capping this at 40 bits.
@@ -35,6 +35,12 @@ pub struct Profile {
     person_count: u64,
     thread_count: Option<usize>,
     model: Model,
+    #[serde(default)]
+    ldap_uri: Option<String>,
+    #[serde(default)]
+    ldap_ca: Option<String>,
+    #[serde(default)]
+    scenario: Option<PathBuf>,
     group: BTreeMap<String, GroupProperties>,
     #[serde(default)]
     dump_raw_data: bool,
@@ -97,6 +103,18 @@ impl Profile {
     pub fn dump_raw_data(&self) -> bool {
         self.dump_raw_data
     }
+
+    pub fn ldap_uri(&self) -> Option<&str> {
+        self.ldap_uri.as_deref()
+    }
+
+    pub fn ldap_ca(&self) -> Option<&String> {
+        self.ldap_ca.as_ref()
+    }
+
+    pub fn scenario(&self) -> Option<&Path> {
+        self.scenario.as_deref()
+    }
 }
 
 pub struct ProfileBuilder {
@@ -113,6 +131,9 @@ pub struct ProfileBuilder {
     pub thread_count: Option<usize>,
     pub model: Model,
     pub dump_raw_data: bool,
+    pub ldap_uri: Option<String>,
+    pub ldap_ca: Option<String>,
+    pub scenario: Option<PathBuf>,
 }
 
 fn validate_u64_bound(value: Option<u64>, default: u64) -> Result<u64, Error> {
@@ -151,6 +172,9 @@ impl ProfileBuilder {
             thread_count,
             model,
             dump_raw_data,
+            ldap_uri: None,
+            ldap_ca: None,
+            scenario: None,
         }
     }
 
@@ -159,6 +183,21 @@ impl ProfileBuilder {
         self
     }
 
+    pub fn ldap_uri(mut self, ldap_uri: Option<String>) -> Self {
+        self.ldap_uri = ldap_uri;
+        self
+    }
+
+    pub fn ldap_ca(mut self, ldap_ca: Option<String>) -> Self {
+        self.ldap_ca = ldap_ca;
+        self
+    }
+
+    pub fn scenario(mut self, scenario: Option<PathBuf>) -> Self {
+        self.scenario = scenario;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn warmup_time(mut self, time: Option<u64>) -> Self {
         self.warmup_time = time;
@@ -197,6 +236,9 @@ impl ProfileBuilder {
             thread_count,
             model,
             dump_raw_data,
+            ldap_uri,
+            ldap_ca,
+            scenario,
         } = self;
 
         let seed: u64 = seed.unwrap_or_else(|| {
@@ -234,6 +276,9 @@ impl ProfileBuilder {
             thread_count,
             group,
             model,
+            ldap_uri,
+            ldap_ca,
+            scenario,
             dump_raw_data,
         })
     }
@@ -1,4 +1,5 @@
 use crate::error::Error;
+use crate::models::mixed::MixedContext;
 use crate::state::*;
 use crate::stats::{BasicStatistics, TestPhase};
 
@@ -13,6 +14,7 @@ use kanidm_client::{KanidmClient, KanidmClientBuilder};
 
 use serde::Serialize;
 use tokio::sync::broadcast;
+use url::Url;
 
 use std::time::{Duration, Instant};
 
@@ -24,11 +26,15 @@ async fn actor_person(
     rng_seed: u64,
     additional_clients: Vec<KanidmClient>,
     warmup_time: Duration,
+    mixed_context: Option<Arc<MixedContext>>,
 ) -> Result<(), Error> {
-    let mut model =
-        person
-            .model
-            .as_dyn_object(rng_seed, additional_clients, &person.username, warmup_time)?;
+    let mut model = person.model.as_dyn_object(
+        rng_seed,
+        additional_clients,
+        &person.username,
+        warmup_time,
+        mixed_context,
+    )?;
 
     while let Err(broadcast::error::TryRecvError::Empty) = actor_rx.try_recv() {
         let events = model.transition(&main_client, &person).await?;
@@ -49,9 +55,10 @@ pub struct EventRecord {
     pub details: EventDetail,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
 pub enum EventDetail {
     Login,
+    LoginTotp,
     Logout,
     PersonSetSelfMail,
     PersonGetSelfAccount,
@@ -60,6 +67,8 @@ pub enum EventDetail {
     PersonReauth,
     PersonCreateGroup,
     PersonAddGroupMembers,
+    PersonSearch,
+    LdapBind,
     GroupReplicationDelay,
     Error,
 }
@@ -134,6 +143,33 @@ async fn execute_inner(
 }
 
 pub async fn execute(state: State, control_rx: broadcast::Receiver<Signal>) -> Result<(), Error> {
+    // Check the mixed model can run before anything is started.
+    let mixed_context = match state.scenario {
+        Some(scenario) => {
+            let ldap_url = state
+                .profile
+                .ldap_uri()
+                .map(Url::parse)
+                .transpose()
+                .map_err(|err| {
+                    error!(?err, "Invalid ldap uri");
+                    Error::Ldap
+                })?;
+
+            if scenario.requires_ldap() && ldap_url.is_none() {
+                error!("The scenario includes LDAP binds, but the profile has no ldap_uri");
+                return Err(Error::Ldap);
+            }
+
+            Some(Arc::new(MixedContext {
+                scenario,
+                ldap_url,
+                ldap_ca: state.profile.ldap_ca().cloned(),
+            }))
+        }
+        None => None,
+    };
+
     // Create a statistics queue.
     let stats_queue = Arc::new(SegQueue::new());
     let stats_ctrl = Arc::new(ArrayQueue::new(4));
@@ -203,6 +239,7 @@ pub async fn execute(state: State, control_rx: broadcast::Receiver<Signal>) -> R
             state.profile.seed(),
             cloned_clients,
             state.profile.warmup_time(),
+            mixed_context.clone(),
         )))
     }
 
@@ -0,0 +1,157 @@
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+const DEFAULT_THINK_TIME: u64 = 1000;
+
+/// An action that an actor of the mixed model can perform once it's signed in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ScenarioAction {
+    /// Sign in again, with a TOTP if the person has one.
+    Login,
+    /// Reauthenticate the current session to refresh its privileges.
+    SessionRefresh,
+    /// Bind to the LDAP interface with the person's Unix password.
+    LdapBind,
+    /// Search for persons by a partial name.
+    Search,
+    /// Read the person's own account.
+    ReadSelfAccount,
+    /// Read the groups the person is a member of.
+    ReadSelfMemberOf,
+}
+
+/// A scenario describes the mix of actions performed by the mixed model. The weights are
+/// relative, so the counts of each kind of request captured from a production system can
+/// be used as-is to replay the same ratios at a different scale.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    /// The mean time in milliseconds each actor waits between actions.
+    #[serde(default = "default_think_time")]
+    think_time: u64,
+    /// The percentage of persons that have a TOTP, and sign in with it.
+    #[serde(default)]
+    totp_percent: u8,
+    weights: ScenarioWeights,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ScenarioWeights {
+    login: u64,
+    session_refresh: u64,
+    ldap_bind: u64,
+    search: u64,
+    read_self_account: u64,
+    read_self_member_of: u64,
+}
+
+fn default_think_time() -> u64 {
+    DEFAULT_THINK_TIME
+}
+
+impl Scenario {
+    pub fn think_time(&self) -> Duration {
+        Duration::from_millis(self.think_time)
+    }
+
+    pub fn totp_percent(&self) -> u8 {
+        self.totp_percent
+    }
+
+    /// The actions with a weight, in a stable order.
+    pub fn weights(&self) -> impl Iterator<Item = (ScenarioAction, u64)> {
+        let ScenarioWeights {
+            login,
+            session_refresh,
+            ldap_bind,
+            search,
+            read_self_account,
+            read_self_member_of,
+        } = self.weights;
+
+        [
+            (ScenarioAction::Login, login),
+            (ScenarioAction::SessionRefresh, session_refresh),
+            (ScenarioAction::LdapBind, ldap_bind),
+            (ScenarioAction::Search, search),
+            (ScenarioAction::ReadSelfAccount, read_self_account),
+            (ScenarioAction::ReadSelfMemberOf, read_self_member_of),
+        ]
+        .into_iter()
+        .filter(|(_, weight)| *weight > 0)
+    }
+
+    pub fn requires_ldap(&self) -> bool {
+        self.weights()
+            .any(|(action, _)| action == ScenarioAction::LdapBind)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.totp_percent > 100 {
+            error!(
+                "totp_percent must be between 0 and 100, but {} was provided",
+                self.totp_percent
+            );
+            return Err(Error::InvalidState);
+        }
+
+        if self.weights().next().is_none() {
+            error!("The scenario must give at least one action a weight");
+            return Err(Error::InvalidState);
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<&Path> for Scenario {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let file_contents = std::fs::read_to_string(path).map_err(|io_err| {
+            error!(?io_err);
+            Error::Io
+        })?;
+
+        let scenario: Scenario = toml::from_str(&file_contents).map_err(|toml_err| {
+            error!(?toml_err);
+            Error::SerdeToml
+        })?;
+        scenario.validate()?;
+
+        Ok(scenario)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Scenario, ScenarioAction};
+
+    #[test]
+    fn test_scenario_sample_parsing() {
+        let scenario: Scenario = toml::from_str(include_str!("../scenario-sample.toml"))
+            .expect("Failed to parse sample scenario");
+
+        assert!(scenario.validate().is_ok());
+        assert!(scenario.requires_ldap());
+        assert!(scenario
+            .weights()
+            .any(|(action, _)| action == ScenarioAction::SessionRefresh));
+    }
+
+    #[test]
+    fn test_scenario_requires_a_weight() {
+        let scenario: Scenario = toml::from_str(
+            r#"
+            [weights]
+            login = 0
+            "#,
+        )
+        .expect("Failed to parse scenario");
+
+        assert!(scenario.validate().is_err());
+        assert!(!scenario.requires_ldap());
+    }
+}
@@ -1,14 +1,18 @@
 use crate::error::Error;
 use crate::model::{ActorModel, ActorRole};
 use crate::models;
+use crate::models::mixed::MixedContext;
 use crate::profile::Profile;
+use crate::scenario::Scenario;
 use core::fmt::Display;
 use kanidm_client::KanidmClient;
+use kanidm_proto::internal::TotpSecret;
 use rand::SeedableRng;
 use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 /// A serialisable state representing the content of a kanidm database and potential
 /// test content that can be created and modified.
@@ -23,6 +27,9 @@ pub struct State {
     pub persons: Vec<Person>,
     pub groups: Vec<Group>,
     pub thread_count: Option<usize>, // oauth_clients: Vec<Oauth2Clients>,
+    /// The workload of the mixed model, copied from the profile's scenario file.
+    #[serde(default)]
+    pub scenario: Option<Scenario>,
 }
 
 impl State {
@@ -59,6 +66,7 @@ impl TryFrom<&Path> for State {
 pub enum Flag {
     DisableAllPersonsMFAPolicy,
     ExtendPrivilegedAuthExpiry,
+    AllowLdapUnixPasswordBind,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -91,6 +99,9 @@ pub enum Model {
     Writer,
     /// This model adds empty group to a sever and measures how long it takes before they are replicated to the other servers
     LatencyMeasurer,
+    /// This model performs a weighted mix of logins, session refreshes, LDAP binds and
+    /// searches as described by a scenario file
+    Mixed,
 }
 
 impl Model {
@@ -100,6 +111,7 @@ impl Model {
         additional_clients: Vec<KanidmClient>,
         person_name: &str,
         warmup_time: Duration,
+        mixed_context: Option<Arc<MixedContext>>,
     ) -> Result<Box<dyn ActorModel + Send + '_>, Error> {
         let cha_rng = ChaCha8Rng::seed_from_u64(rng_seed);
         let warmup_time_as_ms = warmup_time.as_millis() as u64;
@@ -116,13 +128,43 @@ impl Model {
                     warmup_time_as_ms,
                 )?)
             }
+            Model::Mixed => {
+                let Some(mixed_context) = mixed_context else {
+                    error!("The mixed model requires a scenario, but the state has none");
+                    return Err(Error::InvalidState);
+                };
+                Box::new(models::mixed::ActorMixed::new(
+                    rng_seed,
+                    person_name,
+                    warmup_time_as_ms,
+                    mixed_context,
+                )?)
+            }
         })
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Credential {
-    Password { plain: String },
+    Password {
+        plain: String,
+    },
+    /// The TOTP secret is generated by the server, so it's only known once preflight has
+    /// enrolled it.
+    PasswordTotp {
+        plain: String,
+        totp: Option<TotpSecret>,
+    },
+}
+
+impl Credential {
+    pub fn password(&self) -> &str {
+        match self {
+            Credential::Password { plain } | Credential::PasswordTotp { plain, .. } => {
+                plain.as_str()
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -133,6 +175,9 @@ pub struct Person {
     pub roles: BTreeSet<ActorRole>,
     pub credential: Credential,
     pub model: Model,
+    /// If the person has a Unix password, that is used for LDAP binds.
+    #[serde(default)]
+    pub posix: bool,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -4,6 +4,7 @@ use chrono::Local;
 use crossbeam::queue::{ArrayQueue, SegQueue};
 use csv::Writer;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -37,14 +38,18 @@ enum OpKind {
 impl From<EventDetail> for OpKind {
     fn from(value: EventDetail) -> Self {
         match value {
-            EventDetail::PersonGetSelfMemberOf | EventDetail::PersonGetSelfAccount => {
-                OpKind::ReadOp
-            }
+            EventDetail::PersonGetSelfMemberOf
+            | EventDetail::PersonGetSelfAccount
+            | EventDetail::PersonSearch => OpKind::ReadOp,
             EventDetail::PersonSetSelfMail
             | EventDetail::PersonSetSelfPassword
             | EventDetail::PersonCreateGroup
             | EventDetail::PersonAddGroupMembers => OpKind::WriteOp,
-            EventDetail::Login | EventDetail::Logout | EventDetail::PersonReauth => OpKind::Auth,
+            EventDetail::Login
+            | EventDetail::LoginTotp
+            | EventDetail::Logout
+            | EventDetail::PersonReauth
+            | EventDetail::LdapBind => OpKind::Auth,
             EventDetail::GroupReplicationDelay => OpKind::ReplicationDelay,
             EventDetail::Error => OpKind::Error,
         }
@@ -124,6 +129,8 @@ impl DataCollector for BasicStatistics {
         let mut readop_times = Vec::new();
         let mut writeop_times = Vec::new();
         let mut replication_delays = Vec::new();
+        let mut latencies: BTreeMap<EventDetail, Vec<f64>> = BTreeMap::new();
+        let mut error_count: usize = 0;
         let mut raw_stats = Vec::new();
 
         // We will drain this now.
@@ -140,6 +147,15 @@ impl DataCollector for BasicStatistics {
                 ));
             }
 
+            if matches!(event_record.details, EventDetail::Error) {
+                error_count += 1;
+            } else {
+                latencies
+                    .entry(event_record.details)
+                    .or_default()
+                    .push(event_record.duration.as_secs_f64());
+            }
+
             match OpKind::from(event_record.details) {
                 OpKind::ReadOp => {
                     readop_times.push(event_record.duration.as_secs_f64());
@@ -155,7 +171,7 @@ impl DataCollector for BasicStatistics {
             }
         }
 
-        if readop_times.is_empty() && writeop_times.is_empty() && replication_delays.is_empty() {
+        if latencies.is_empty() {
             error!("For some weird reason no valid data was recorded in this benchmark, bailing out...");
             return Err(Error::InvalidState);
         }
@@ -198,6 +214,26 @@ impl DataCollector for BasicStatistics {
         info!("SD: {} seconds", stats.replication_delay_sd);
         info!("95%: {}", stats.replication_delay_95);
 
+        let latency_percentiles = latencies
+            .into_iter()
+            .map(|(operation, times)| LatencyPercentiles::new(operation, times))
+            .collect::<Vec<_>>();
+
+        for latency in latency_percentiles.iter() {
+            info!(
+                "{:?}: {} events, p50: {} p90: {} p95: {} p99: {} max: {} seconds",
+                latency.operation,
+                latency.events,
+                latency.p50,
+                latency.p90,
+                latency.p95,
+                latency.p99,
+                latency.max
+            );
+        }
+
+        info!("Received {} errors", error_count);
+
         let now = Local::now();
         let filepath = format!("orca-run-{}.csv", now.to_rfc3339());
 
@@ -206,6 +242,14 @@ impl DataCollector for BasicStatistics {
         let mut wrt = Writer::from_path(filepath).map_err(|_| Error::Io)?;
         wrt.serialize(stats).map_err(|_| Error::Io)?;
 
+        let latency_filepath = format!("orca-run-{}-latency.csv", now.to_rfc3339());
+        info!("Now saving latency percentiles as '{latency_filepath}'");
+
+        let mut wrt = Writer::from_path(latency_filepath).map_err(|_| Error::Io)?;
+        for latency in latency_percentiles.iter() {
+            wrt.serialize(latency).map_err(|_| Error::Io)?;
+        }
+
         if dump_raw_data {
             let raw_data_filepath = format!("orca-run-{}-raw.csv", now.to_rfc3339());
             info!("Now saving raw data as '{raw_data_filepath}'");
@@ -235,11 +279,49 @@ impl SerializableEventRecord {
         SerializableEventRecord {
             time_from_start_ms: event_record.start.duration_since(test_start).as_millis(),
             duration_ms: event_record.duration.as_millis(),
-            details: event_record.details.clone(),
+            details: event_record.details,
         }
     }
 }
 
+/// The latency of one kind of operation. Unlike the mean and standard deviation these don't
+/// assume a normal distribution, which matters for the long tail of slow requests.
+#[derive(Serialize)]
+struct LatencyPercentiles {
+    operation: EventDetail,
+    events: usize,
+    p50: f64,
+    p90: f64,
+    p95: f64,
+    p99: f64,
+    max: f64,
+}
+
+impl LatencyPercentiles {
+    fn new(operation: EventDetail, mut times: Vec<f64>) -> Self {
+        times.sort_unstable_by(f64::total_cmp);
+
+        LatencyPercentiles {
+            operation,
+            events: times.len(),
+            p50: Self::percentile(&times, 50.),
+            p90: Self::percentile(&times, 90.),
+            p95: Self::percentile(&times, 95.),
+            p99: Self::percentile(&times, 99.),
+            max: times.last().copied().unwrap_or_default(),
+        }
+    }
+
+    // Nearest rank of the sorted times.
+    fn percentile(sorted_times: &[f64], percentile: f64) -> f64 {
+        let rank = ((percentile / 100.) * sorted_times.len() as f64).ceil() as usize;
+        sorted_times
+            .get(rank.saturating_sub(1))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Serialize)]
 struct StatsContainer {
     node_count: usize,
@@ -330,3 +412,25 @@ impl StatsContainer {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::LatencyPercentiles;
+    use crate::run::EventDetail;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let times = (1..=100).rev().map(|t| t as f64).collect();
+        let latency = LatencyPercentiles::new(EventDetail::Login, times);
+
+        assert_eq!(latency.events, 100);
+        assert_eq!(latency.p50, 50.);
+        assert_eq!(latency.p90, 90.);
+        assert_eq!(latency.p99, 99.);
+        assert_eq!(latency.max, 100.);
+
+        let empty = LatencyPercentiles::new(EventDetail::Login, Vec::new());
+        assert_eq!(empty.events, 0);
+        assert_eq!(empty.p50, 0.);
+    }
+}
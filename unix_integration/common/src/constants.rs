@@ -7,16 +7,21 @@ pub const DEFAULT_PERSISTENT_DB_PATH: &str = "/var/lib/kanidm-unixd/kanidm.db";
 pub const DEFAULT_CACHE_DB_PATH: &str = "/var/cache/kanidm-unixd/kanidm.cache.db";
 pub const DEFAULT_CONN_TIMEOUT: u64 = 2;
 pub const DEFAULT_CACHE_TIMEOUT: u64 = 15;
+pub const DEFAULT_KIOSK_CACHE_TIMEOUT: u64 = 10;
 pub const DEFAULT_SHELL: &str = env!("KANIDM_RESOLVER_UNIX_SHELL_PATH");
 pub const DEFAULT_HOME_PREFIX: &str = "/home/";
 pub const DEFAULT_HOME_ATTR: HomeAttr = HomeAttr::Uuid;
 pub const DEFAULT_HOME_ALIAS: Option<HomeAttr> = Some(HomeAttr::Spn);
 pub const DEFAULT_USE_ETC_SKEL: bool = false;
+pub const DEFAULT_HOME_SKELETON_PREFIX: &str = "/etc/kanidm/skel/";
 pub const DEFAULT_UID_ATTR_MAP: UidAttr = UidAttr::Spn;
 pub const DEFAULT_GID_ATTR_MAP: UidAttr = UidAttr::Spn;
 pub const DEFAULT_SELINUX: bool = true;
 pub const DEFAULT_TPM_TCTI_NAME: &str = "device:/dev/tpmrm0";
 pub const DEFAULT_HSM_PIN_PATH: &str = "/var/lib/kanidm-unixd/hsm-pin";
+/// The credential caches of accounts are stored at this prefix followed by their uid, which
+/// is the default location used by MIT kerberos.
+pub const KERBEROS_CCACHE_PREFIX: &str = "/tmp/krb5cc_";
 
 #[cfg(all(target_family = "unix", not(target_os = "freebsd")))]
 pub const DEFAULT_SHELL_SEARCH_PATHS: &[&str] = &["/bin"];
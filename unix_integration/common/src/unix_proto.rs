@@ -1,6 +1,7 @@
 use crate::unix_passwd::{EtcGroup, EtcUser};
 use kanidm_proto::internal::OperationError;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NssUser {
@@ -126,11 +127,23 @@ pub enum ClientRequest {
         info: PamServiceInfo,
     },
     PamAuthenticateStep(PamAuthRequest),
-    PamAccountAllowed(String),
+    PamAccountAllowed {
+        account_id: String,
+        /// The pam service that the account is accessing, if known.
+        service: Option<String>,
+    },
     PamAccountBeginSession(String),
+    PamAccountEndSession(String),
     InvalidateCache,
     ClearCache,
     Status,
+    /// Enroll this machine as a host, using a join token issued by an administrator.
+    Enroll {
+        name: String,
+        join_token: String,
+    },
+    /// Retrieve the keytab of the kerberos service principals of this host.
+    KerberosKeytab,
 }
 
 impl ClientRequest {
@@ -152,13 +165,21 @@ impl ClientRequest {
                 info.rhost.as_deref().unwrap_or("")
             ),
             ClientRequest::PamAuthenticateStep(_) => "PamAuthenticateStep".to_string(),
-            ClientRequest::PamAccountAllowed(id) => {
-                format!("PamAccountAllowed({})", id)
-            }
+            ClientRequest::PamAccountAllowed {
+                account_id,
+                service,
+            } => format!(
+                "PamAccountAllowed{{ account_id={} pam_service={} }}",
+                account_id,
+                service.as_deref().unwrap_or("")
+            ),
             ClientRequest::PamAccountBeginSession(_) => "PamAccountBeginSession".to_string(),
+            ClientRequest::PamAccountEndSession(_) => "PamAccountEndSession".to_string(),
             ClientRequest::InvalidateCache => "InvalidateCache".to_string(),
             ClientRequest::ClearCache => "ClearCache".to_string(),
             ClientRequest::Status => "Status".to_string(),
+            ClientRequest::Enroll { name, .. } => format!("Enroll{{ name={} }}", name),
+            ClientRequest::KerberosKeytab => "KerberosKeytab".to_string(),
         }
     }
 }
@@ -182,6 +203,8 @@ pub enum ClientResponse {
 
     ProviderStatus(Vec<ProviderStatus>),
 
+    KerberosKeytab(Vec<u8>),
+
     Ok,
     Error(OperationError),
 }
@@ -198,16 +221,59 @@ pub struct HomeDirectoryInfo {
     pub gid: u32,
     pub name: String,
     pub aliases: Vec<String>,
+    /// The name of the skeleton to create the home directory from.
+    #[serde(default)]
+    pub skeleton: Option<String>,
+    /// The filesystem quota of the home directory in MiB.
+    #[serde(default)]
+    pub quota: Option<u32>,
+}
+
+/// The kerberos tickets that were issued to an account as it began a session.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KerberosCredentialCacheInfo {
+    pub uid: u32,
+    pub gid: u32,
+    /// The credential cache, in the file format of MIT kerberos.
+    pub ccache: Vec<u8>,
+}
+
+impl fmt::Debug for KerberosCredentialCacheInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The credential cache contains session keys, so it is never logged.
+        f.debug_struct("KerberosCredentialCacheInfo")
+            .field("uid", &self.uid)
+            .field("gid", &self.gid)
+            .finish_non_exhaustive()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum TaskRequest {
     HomeDirectory(HomeDirectoryInfo),
+    /// Remove the home directory of a guest account at the end of their last session.
+    RemoveHomeDirectory(HomeDirectoryInfo),
+    /// Store the kerberos tickets of an account in its credential cache.
+    KerberosCredentialCache(KerberosCredentialCacheInfo),
+}
+
+/// The actions that were taken to prepare a home directory.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct HomeDirectoryReport {
+    pub created: bool,
+    /// The skeleton that the home directory was created from, if any.
+    pub skeleton: Option<String>,
+    pub quota_applied: bool,
+    /// Set if the ownership of an existing home directory was corrected.
+    pub ownership_fixed: bool,
+    /// Problems that did not prevent the home directory from being used.
+    pub warnings: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum TaskResponse {
     Success,
+    HomeDirectory(HomeDirectoryReport),
     Error(String),
 }
 
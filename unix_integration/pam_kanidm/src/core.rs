@@ -13,7 +13,7 @@ use kanidm_unix_common::unix_proto::{
 use std::time::Duration;
 use time::OffsetDateTime;
 
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 #[cfg(test)]
 use kanidm_unix_common::client_sync::UnixStream;
@@ -418,7 +418,19 @@ pub fn acct_mgmt<P: PamHandler>(
 
     match req_opt.connect_to_daemon() {
         Source::Daemon(mut daemon_client) => {
-            let req = ClientRequest::PamAccountAllowed(account_id);
+            // The service is used to evaluate host based access control rules. If it
+            // can't be determined, only rules that apply to all services will allow access.
+            let service = match pamh.service_info() {
+                Ok(info) => Some(info.service),
+                Err(err) => {
+                    warn!(?err, "unable to determine pam service");
+                    None
+                }
+            };
+            let req = ClientRequest::PamAccountAllowed {
+                account_id,
+                service,
+            };
             match daemon_client.call_and_wait(&req, None) {
                 Ok(r) => match r {
                     ClientResponse::PamStatus(Some(true)) => {
@@ -524,8 +536,39 @@ pub fn sm_open_session<P: PamHandler>(
     }
 }
 
-pub fn sm_close_session<P: PamHandler>(_pamh: &P, _opts: &ModuleOptions) -> PamResultCode {
-    PamResultCode::PAM_SUCCESS
+pub fn sm_close_session<P: PamHandler>(
+    pamh: &P,
+    _opts: &ModuleOptions,
+    req_opt: RequestOptions,
+) -> PamResultCode {
+    match req_opt.connect_to_daemon() {
+        Source::Daemon(mut daemon_client) => {
+            let account_id = match pamh.account_id() {
+                Ok(acc) => acc,
+                Err(err) => return err,
+            };
+
+            let req = ClientRequest::PamAccountEndSession(account_id);
+
+            match daemon_client.call_and_wait(&req, None) {
+                Ok(ClientResponse::Ok) => {
+                    debug!("PAM_SUCCESS");
+                    PamResultCode::PAM_SUCCESS
+                }
+                other => {
+                    debug!(err = ?other, "PAM_IGNORE");
+                    PamResultCode::PAM_IGNORE
+                }
+            }
+        }
+        Source::Fallback {
+            users: _,
+            shadow: _,
+        } => {
+            debug!("PAM_SUCCESS");
+            PamResultCode::PAM_SUCCESS
+        }
+    }
 }
 
 pub fn sm_chauthtok<P: PamHandler>(_pamh: &P, _opts: &ModuleOptions) -> PamResultCode {
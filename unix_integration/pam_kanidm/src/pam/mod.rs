@@ -169,7 +169,11 @@ impl PamHooks for PamKanidm {
 
         debug!(?args, ?opts, "sm_close_session");
 
-        core::sm_close_session(pamh, &opts)
+        let req_opt = RequestOptions::Main {
+            config_path: DEFAULT_CONFIG_PATH,
+        };
+
+        core::sm_close_session(pamh, &opts, req_opt)
     }
 
     fn sm_chauthtok(pamh: &PamHandle, args: Vec<&CStr>, _flags: PamFlag) -> PamResultCode {
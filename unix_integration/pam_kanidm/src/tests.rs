@@ -422,13 +422,13 @@ fn pam_fallback_sm_open_session() {
 
 #[test]
 fn pam_fallback_sm_close_session() {
-    // let req_opt = RequestOptions::fallback_fixture();
+    let req_opt = RequestOptions::fallback_fixture();
     let mod_opts = ModuleOptions::default();
 
     let pamh = TestHandler::default();
 
     assert_eq!(
-        core::sm_close_session(&pamh, &mod_opts),
+        core::sm_close_session(&pamh, &mod_opts, req_opt),
         PamResultCode::PAM_SUCCESS
     );
 }
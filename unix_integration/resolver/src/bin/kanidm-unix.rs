@@ -13,6 +13,9 @@
 #[macro_use]
 extern crate tracing;
 
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
 use std::process::ExitCode;
 
 use clap::Parser;
@@ -77,6 +80,8 @@ async fn main() -> ExitCode {
         KanidmUnixOpt::CacheClear { debug, really: _ } => debug,
         KanidmUnixOpt::CacheInvalidate { debug } => debug,
         KanidmUnixOpt::Status { debug } => debug,
+        KanidmUnixOpt::Enroll { debug, .. } => debug,
+        KanidmUnixOpt::KerberosKeytab { debug, .. } => debug,
         KanidmUnixOpt::Version { debug } => debug,
     };
 
@@ -164,7 +169,10 @@ async fn main() -> ExitCode {
                 }
             }
 
-            let sereq = ClientRequest::PamAccountAllowed(account_id);
+            let sereq = ClientRequest::PamAccountAllowed {
+                account_id,
+                service: Some("kanidm-unix".to_string()),
+            };
 
             match daemon_client.call(&sereq, None).await {
                 Ok(r) => match r {
@@ -262,6 +270,80 @@ async fn main() -> ExitCode {
             }
             ExitCode::SUCCESS
         }
+        KanidmUnixOpt::Enroll {
+            debug: _,
+            name,
+            join_token,
+        } => {
+            debug!("Starting enrollment tool ...");
+
+            let mut daemon_client = setup_client!();
+
+            let req = ClientRequest::Enroll { name, join_token };
+
+            match daemon_client.call(&req, None).await {
+                Ok(ClientResponse::Ok) => {
+                    println!("success");
+                    ExitCode::SUCCESS
+                }
+                Ok(ClientResponse::Error(err)) => {
+                    error!("Error -> {}", err);
+                    ExitCode::FAILURE
+                }
+                Ok(r) => {
+                    error!("Error: unexpected response -> {:?}", r);
+                    ExitCode::FAILURE
+                }
+                Err(e) => {
+                    error!("Error -> {:?}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        KanidmUnixOpt::KerberosKeytab { debug: _, output } => {
+            debug!("Starting kerberos keytab tool ...");
+
+            let mut daemon_client = setup_client!();
+
+            let keytab = match daemon_client
+                .call(&ClientRequest::KerberosKeytab, None)
+                .await
+            {
+                Ok(ClientResponse::KerberosKeytab(keytab)) => keytab,
+                Ok(ClientResponse::Error(err)) => {
+                    error!("Error -> {}", err);
+                    return ExitCode::FAILURE;
+                }
+                Ok(r) => {
+                    error!("Error: unexpected response -> {:?}", r);
+                    return ExitCode::FAILURE;
+                }
+                Err(e) => {
+                    error!("Error -> {:?}", e);
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            // The keytab holds the long term keys of the services, so only root may read it.
+            let written = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&output)
+                .and_then(|mut file| file.write_all(&keytab));
+
+            match written {
+                Ok(()) => {
+                    println!("Wrote kerberos keytab to {}", output.display());
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    error!("Unable to write {} -> {:?}", output.display(), e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
         KanidmUnixOpt::Version { debug: _ } => {
             println!("kanidm-unix {}", env!("KANIDM_PKG_VERSION"));
             ExitCode::SUCCESS
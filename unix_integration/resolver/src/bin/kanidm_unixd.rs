@@ -64,6 +64,9 @@ static ALLOC: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 type AsyncTaskRequest = (TaskRequest, oneshot::Sender<()>);
 
+// How often to check if the machine credentials of the providers need to be rotated.
+const MACHINE_IDENTITY_REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+
 #[derive(Default)]
 struct ClientCodec;
 
@@ -184,6 +187,19 @@ async fn handle_task_client(
                 // Ignore if it fails.
                 let _ = v.1.send(());
             }
+            Some(Ok(TaskResponse::HomeDirectory(report))) => {
+                if report.warnings.is_empty() {
+                    info!(?report, "Home directory task completed.");
+                } else {
+                    warn!(?report, "Home directory task completed with warnings.");
+                }
+                let _ = v.1.send(());
+            }
+            Some(Ok(TaskResponse::Error(msg))) => {
+                // The task failed, but the tasks daemon is still available. Dropping the
+                // one-shot reports the failure to the waiting client.
+                error!(%msg, "Task failed.");
+            }
             other => {
                 error!("Error -> {:?}", other);
                 return Err(Box::new(IoError::new(ErrorKind::Other, "oh no!")));
@@ -192,6 +208,48 @@ async fn handle_task_client(
     }
 }
 
+/// Request the kerberos tickets of an account that is beginning a session, and have the task
+/// daemon store them in the credential cache of the account. Kerberos is only needed by some
+/// services, so a failure here does not prevent the session from beginning.
+async fn store_kerberos_credential_cache(
+    cachelayer: &Resolver,
+    task_channel_tx: &Sender<AsyncTaskRequest>,
+    account_id: &str,
+) {
+    let info = match cachelayer.kerberos_credential_cache(account_id).await {
+        Ok(Some(info)) => info,
+        Ok(None) => return,
+        Err(_) => {
+            warn!("Unable to request kerberos tickets, continuing without them");
+            return;
+        }
+    };
+
+    let (tx, rx) = oneshot::channel();
+
+    if task_channel_tx
+        .send_timeout(
+            (TaskRequest::KerberosCredentialCache(info), tx),
+            Duration::from_millis(100),
+        )
+        .await
+        .is_err()
+    {
+        warn!("Unable to submit kerberos credential cache task");
+        return;
+    }
+
+    match tokio::time::timeout_at(
+        tokio::time::Instant::now() + Duration::from_millis(1000),
+        rx,
+    )
+    .await
+    {
+        Ok(Ok(_)) => debug!("Stored kerberos credential cache"),
+        _ => warn!("Timed out storing kerberos credential cache"),
+    }
+}
+
 async fn handle_client(
     sock: UnixStream,
     cachelayer: Arc<Resolver>,
@@ -324,15 +382,18 @@ async fn handle_client(
                     ClientResponse::Error(OperationError::KU002ContinueWhileSessionInActive)
                 }
             },
-            ClientRequest::PamAccountAllowed(account_id) => cachelayer
-                .pam_account_allowed(account_id.as_str())
+            ClientRequest::PamAccountAllowed {
+                account_id,
+                service,
+            } => cachelayer
+                .pam_account_allowed(account_id.as_str(), service.as_deref())
                 .await
                 .map(ClientResponse::PamStatus)
                 .unwrap_or(ClientResponse::Error(
                     OperationError::KU005ErrorCheckingAccount,
                 )),
             ClientRequest::PamAccountBeginSession(account_id) => {
-                match cachelayer
+                let resp = match cachelayer
                     .pam_account_beginsession(account_id.as_str())
                     .await
                 {
@@ -375,6 +436,49 @@ async fn handle_client(
                         ClientResponse::Ok
                     }
                     Err(_) => ClientResponse::Error(OperationError::KU005ErrorCheckingAccount),
+                };
+
+                if matches!(resp, ClientResponse::Ok) {
+                    store_kerberos_credential_cache(&cachelayer, task_channel_tx, &account_id)
+                        .await;
+                }
+
+                resp
+            }
+            ClientRequest::PamAccountEndSession(account_id) => {
+                match cachelayer.pam_account_endsession(account_id.as_str()).await {
+                    Ok(Some(info)) => {
+                        let (tx, rx) = oneshot::channel();
+
+                        match task_channel_tx
+                            .send_timeout(
+                                (TaskRequest::RemoveHomeDirectory(info), tx),
+                                Duration::from_millis(100),
+                            )
+                            .await
+                        {
+                            Ok(()) => {
+                                match tokio::time::timeout_at(
+                                    tokio::time::Instant::now() + Duration::from_millis(5000),
+                                    rx,
+                                )
+                                .await
+                                {
+                                    Ok(Ok(_)) => {
+                                        debug!("Task completed, returning to pam ...");
+                                        ClientResponse::Ok
+                                    }
+                                    _ => ClientResponse::Error(OperationError::KG001TaskTimeout),
+                                }
+                            }
+                            Err(_) => ClientResponse::Error(OperationError::KG002TaskCommFailure),
+                        }
+                    }
+                    Ok(None) => {
+                        // The session has ended, and there is nothing to clean up.
+                        ClientResponse::Ok
+                    }
+                    Err(_) => ClientResponse::Error(OperationError::KU005ErrorCheckingAccount),
                 }
             }
             ClientRequest::InvalidateCache => cachelayer
@@ -398,6 +502,35 @@ async fn handle_client(
                 let status = cachelayer.provider_status().await;
                 ClientResponse::ProviderStatus(status)
             }
+            ClientRequest::Enroll { name, join_token } => {
+                if ucred.uid() == 0 {
+                    cachelayer
+                        .configure_machine_identity(&name, &join_token)
+                        .await
+                        .map(|_| ClientResponse::Ok)
+                        .unwrap_or(ClientResponse::Error(
+                            OperationError::KU007MachineEnrollmentFailed,
+                        ))
+                } else {
+                    error!("{}", OperationError::KU006OnlyRootAllowed);
+                    ClientResponse::Error(OperationError::KU006OnlyRootAllowed)
+                }
+            }
+            ClientRequest::KerberosKeytab => {
+                // The keytab contains the long term keys of the services of this host.
+                if ucred.uid() == 0 {
+                    cachelayer
+                        .kerberos_keytab()
+                        .await
+                        .map(ClientResponse::KerberosKeytab)
+                        .unwrap_or(ClientResponse::Error(
+                            OperationError::KU008KerberosKeytabFailed,
+                        ))
+                } else {
+                    error!("{}", OperationError::KU006OnlyRootAllowed);
+                    ClientResponse::Error(OperationError::KU006OnlyRootAllowed)
+                }
+            }
         };
         reqs.send(resp).await?;
         reqs.flush().await?;
@@ -505,28 +638,30 @@ fn open_tpm(_tcti_name: &str) -> Option<BoxedDynTpm> {
     None
 }
 
+/// Open the hardware tpm, or the soft tpm if it is not available. The flag is true when the
+/// hardware tpm was opened.
 #[cfg(feature = "tpm")]
-fn open_tpm_if_possible(tcti_name: &str) -> BoxedDynTpm {
+fn open_tpm_if_possible(tcti_name: &str) -> (BoxedDynTpm, bool) {
     use kanidm_hsm_crypto::tpm::TpmTss;
     match TpmTss::new(tcti_name) {
         Ok(tpm) => {
             debug!("opened hw tpm");
-            BoxedDynTpm::new(tpm)
+            (BoxedDynTpm::new(tpm), true)
         }
         Err(tpm_err) => {
             warn!(
                 ?tpm_err,
-                "Unable to open requested tpm device, falling back to soft tpm"
+                "Unable to open requested tpm device, falling back to soft tpm. Credentials will not be cached for offline authentication"
             );
-            BoxedDynTpm::new(SoftTpm::new())
+            (BoxedDynTpm::new(SoftTpm::new()), false)
         }
     }
 }
 
 #[cfg(not(feature = "tpm"))]
-fn open_tpm_if_possible(_tcti_name: &str) -> BoxedDynTpm {
-    debug!("opened soft tpm");
-    BoxedDynTpm::new(SoftTpm::new())
+fn open_tpm_if_possible(_tcti_name: &str) -> (BoxedDynTpm, bool) {
+    warn!("Using a soft tpm, credentials will not be cached for offline authentication");
+    (BoxedDynTpm::new(SoftTpm::new()), false)
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -864,16 +999,20 @@ async fn main() -> ExitCode {
                 }
             };
 
-            let mut hsm: BoxedDynTpm = match cfg.hsm_type {
+            // Credentials are only cached when they can be sealed by a hardware tpm. The pin of
+            // the soft tpm is stored on the same disk as the cache, so it would not protect them
+            // from extraction if this device's storage is stolen.
+            let (mut hsm, hsm_is_hardware): (BoxedDynTpm, bool) = match cfg.hsm_type {
                 HsmType::Soft => {
-                    BoxedDynTpm::new(SoftTpm::new())
+                    warn!("Using a soft hsm, credentials will not be cached for offline authentication");
+                    (BoxedDynTpm::new(SoftTpm::new()), false)
                 }
                 HsmType::TpmIfPossible => {
                     open_tpm_if_possible(&cfg.tpm_tcti_name)
                 }
                 HsmType::Tpm => {
                     match open_tpm(&cfg.tpm_tcti_name) {
-                        Some(hsm) => hsm,
+                        Some(hsm) => (hsm, true),
                         None => return ExitCode::FAILURE,
                     }
                 }
@@ -945,7 +1084,8 @@ async fn main() -> ExitCode {
                     SystemTime::now(),
                     &mut (&mut db_txn).into(),
                     &mut hsm,
-                    &machine_key
+                    &machine_key,
+                    hsm_is_hardware,
                 ) else {
                     error!("Failed to configure Kanidm Provider");
                     return ExitCode::FAILURE
@@ -1135,6 +1275,26 @@ async fn main() -> ExitCode {
                 info!("Stopped inotify watcher");
             });
 
+            let mut c_broadcast_rx = broadcast_tx.subscribe();
+
+            let identity_cachelayer = cachelayer.clone();
+            let task_d = tokio::spawn(async move {
+                let mut interval = tokio::time::interval(MACHINE_IDENTITY_REFRESH_INTERVAL);
+                loop {
+                    tokio::select! {
+                        _ = c_broadcast_rx.recv() => {
+                            break;
+                        }
+                        _ = interval.tick() => {
+                            if identity_cachelayer.refresh_machine_identity().await.is_err() {
+                                error!("Failed to refresh machine identity");
+                            }
+                        }
+                    }
+                }
+                info!("Stopped machine identity refresh");
+            });
+
             // Set the umask while we open the path for most clients.
             let before = unsafe { umask(0) };
             let listener = match UnixListener::bind(cfg.sock_path.as_str()) {
@@ -1239,6 +1399,7 @@ async fn main() -> ExitCode {
             let _ = task_a.await;
             let _ = task_b.await;
             let _ = task_c.await;
+            let _ = task_d.await;
 
             ExitCode::SUCCESS
     })
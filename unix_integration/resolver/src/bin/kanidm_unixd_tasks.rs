@@ -11,17 +11,20 @@
 #![deny(clippy::trivially_copy_pass_by_ref)]
 
 use std::ffi::CString;
+use std::io::Write;
 use std::os::unix::ffi::OsStrExt;
-use std::os::unix::fs::symlink;
+use std::os::unix::fs::{fchown, symlink, MetadataExt, OpenOptionsExt};
 use std::path::{Path, PathBuf};
-use std::process::ExitCode;
+use std::process::{Command, ExitCode};
 use std::time::Duration;
 use std::{fs, io};
 
 use bytes::{BufMut, BytesMut};
 use futures::{SinkExt, StreamExt};
-use kanidm_unix_common::constants::DEFAULT_CONFIG_PATH;
-use kanidm_unix_common::unix_proto::{HomeDirectoryInfo, TaskRequest, TaskResponse};
+use kanidm_unix_common::constants::{DEFAULT_CONFIG_PATH, KERBEROS_CCACHE_PREFIX};
+use kanidm_unix_common::unix_proto::{
+    HomeDirectoryInfo, HomeDirectoryReport, KerberosCredentialCacheInfo, TaskRequest, TaskResponse,
+};
 use kanidm_unix_resolver::unix_config::UnixdConfig;
 use kanidm_utils_users::{get_effective_gid, get_effective_uid};
 use libc::{lchown, umask};
@@ -37,6 +40,9 @@ use walkdir::WalkDir;
 #[cfg(all(target_family = "unix", feature = "selinux"))]
 use kanidm_unix_resolver::selinux_util;
 
+/// The system skeleton, used when `use_etc_skel` is enabled.
+const ETC_SKEL_DIR: &str = "/etc/skel";
+
 struct TaskCodec;
 
 impl Decoder for TaskCodec {
@@ -86,13 +92,138 @@ fn chown(path: &Path, gid: u32) -> Result<(), String> {
     Ok(())
 }
 
+/// Copy the content of a skeleton directory into a new home directory.
+fn copy_skeleton(
+    skel_dir: &Path,
+    hd_mount_path: &Path,
+    gid: u32,
+    #[cfg(all(target_family = "unix", feature = "selinux"))] labeler: &selinux_util::SelinuxLabeler,
+) -> Result<(), String> {
+    for entry in WalkDir::new(skel_dir).into_iter().filter_map(|e| e.ok()) {
+        let dest = &hd_mount_path.join(
+            entry
+                .path()
+                .strip_prefix(skel_dir)
+                .map_err(|e| e.to_string())?,
+        );
+
+        #[cfg(all(target_family = "unix", feature = "selinux"))]
+        {
+            let p = entry
+                .path()
+                .strip_prefix(skel_dir)
+                .map_err(|e| e.to_string())?;
+            labeler.label_path(p)?;
+        }
+
+        if entry.path().is_dir() {
+            fs::create_dir_all(dest).map_err(|e| {
+                error!(err = ?e, ?dest, ?skel_dir, "Unable to create directory from skeleton");
+                e.to_string()
+            })?;
+        } else {
+            fs::copy(entry.path(), dest).map_err(|e| {
+                error!(err = ?e, ?dest, ?skel_dir, "Unable to copy from skeleton");
+                e.to_string()
+            })?;
+        }
+        chown(dest, gid)?;
+
+        // Create equivalence rule in the SELinux policy
+        #[cfg(all(target_family = "unix", feature = "selinux"))]
+        labeler.setup_equivalence_rule(hd_mount_path)?;
+    }
+    Ok(())
+}
+
+/// Change the ownership of everything within a home directory. Symlinks are not followed.
+fn fix_ownership(hd_mount_path: &Path, gid: u32) -> Result<(), String> {
+    for entry in WalkDir::new(hd_mount_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        chown(entry.path(), gid)?;
+    }
+    Ok(())
+}
+
+/// Select the skeleton that a new home directory is created from. This is the skeleton named by
+/// the account policy, or `etc_skel_dir` if that is enabled and no named skeleton is usable. The
+/// skeleton name is sanitised in the same way as the home directory name.
+fn select_skeleton(
+    skeleton: Option<&str>,
+    home_skeleton_prefix_path: &Path,
+    etc_skel_dir: &Path,
+    use_etc_skel: bool,
+    warnings: &mut Vec<String>,
+) -> Option<(String, PathBuf)> {
+    let named_skel_dir = skeleton.and_then(|skeleton| {
+        let skeleton = skeleton.trim_start_matches('.').replace(['/', '\\'], "");
+        let skel_dir = home_skeleton_prefix_path.join(&skeleton);
+        if !skeleton.is_empty() && skel_dir.is_dir() {
+            Some((skeleton, skel_dir))
+        } else {
+            warn!(?skel_dir, "home directory skeleton does not exist");
+            warnings.push(format!("skeleton {} does not exist", skeleton));
+            None
+        }
+    });
+
+    named_skel_dir.or_else(|| {
+        (use_etc_skel && etc_skel_dir.exists()).then(|| {
+            (
+                etc_skel_dir.display().to_string(),
+                etc_skel_dir.to_path_buf(),
+            )
+        })
+    })
+}
+
+/// The number of 1KiB blocks in a quota of `quota` MiB.
+fn quota_blocks(quota: u32) -> u64 {
+    u64::from(quota) * 1024
+}
+
+/// The `setquota` command that limits `uid` to `quota` MiB on the filesystem at `mount_path`.
+fn setquota_command(uid: u32, quota: u32, mount_path: &Path) -> Command {
+    // setquota takes limits in 1KiB blocks. The soft and hard limits are the same.
+    let blocks = quota_blocks(quota).to_string();
+
+    let mut command = Command::new("setquota");
+    command
+        .arg("-u")
+        .arg(uid.to_string())
+        .args([blocks.as_str(), blocks.as_str(), "0", "0"])
+        .arg(mount_path);
+    command
+}
+
+/// Apply a user quota in MiB to the filesystem that contains the home directories.
+fn apply_quota(uid: u32, quota: u32, mount_path: &Path) -> Result<(), String> {
+    let output = setquota_command(uid, quota, mount_path)
+        .output()
+        .map_err(|e| format!("Unable to run setquota - {:?}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "setquota failed - {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
 fn create_home_directory(
     info: &HomeDirectoryInfo,
     home_prefix_path: &Path,
     home_mount_prefix_path: Option<&PathBuf>,
+    home_skeleton_prefix_path: &Path,
     use_etc_skel: bool,
     use_selinux: bool,
-) -> Result<(), String> {
+) -> Result<HomeDirectoryReport, String> {
+    let mut report = HomeDirectoryReport::default();
+
     // Final sanity check to prevent certain classes of attacks. This should *never*
     // be possible, but we assert this to be sure.
     let name = info.name.trim_start_matches('.').replace(['/', '\\'], "");
@@ -165,44 +296,49 @@ fn create_home_directory(
         let _ = unsafe { umask(before) };
 
         chown(&hd_mount_path, info.gid)?;
+        report.created = true;
 
-        // Copy in structure from /etc/skel/ if present
-        let skel_dir = Path::new("/etc/skel/");
-        if use_etc_skel && skel_dir.exists() {
-            info!("preparing homedir using /etc/skel");
-            for entry in WalkDir::new(skel_dir).into_iter().filter_map(|e| e.ok()) {
-                let dest = &hd_mount_path.join(
-                    entry
-                        .path()
-                        .strip_prefix(skel_dir)
-                        .map_err(|e| e.to_string())?,
-                );
+        // Copy in the skeleton selected by the account policy, or /etc/skel if enabled.
+        let skel = select_skeleton(
+            info.skeleton.as_deref(),
+            home_skeleton_prefix_path,
+            Path::new(ETC_SKEL_DIR),
+            use_etc_skel,
+            &mut report.warnings,
+        );
 
+        if let Some((skeleton, skel_dir)) = skel {
+            info!(?skel_dir, "preparing homedir using skeleton");
+            copy_skeleton(
+                &skel_dir,
+                &hd_mount_path,
+                info.gid,
                 #[cfg(all(target_family = "unix", feature = "selinux"))]
-                {
-                    let p = entry
-                        .path()
-                        .strip_prefix(skel_dir)
-                        .map_err(|e| e.to_string())?;
-                    labeler.label_path(p)?;
-                }
+                &labeler,
+            )?;
+            report.skeleton = Some(skeleton);
+        }
+    } else {
+        // The directory may have been renamed from another account, or the gidnumber of the
+        // account has changed. Either way, the account must own its home directory.
+        let metadata = fs::symlink_metadata(&hd_mount_path).map_err(|e| {
+            error!(err = ?e, ?hd_mount_path, "Unable to read home directory metadata");
+            format!("{:?}", e)
+        })?;
 
-                if entry.path().is_dir() {
-                    fs::create_dir_all(dest).map_err(|e| {
-                        error!(err = ?e, ?dest, "Unable to create directory from /etc/skel");
-                        e.to_string()
-                    })?;
-                } else {
-                    fs::copy(entry.path(), dest).map_err(|e| {
-                        error!(err = ?e, ?dest, "Unable to copy from /etc/skel");
-                        e.to_string()
-                    })?;
-                }
-                chown(dest, info.gid)?;
+        if metadata.is_dir() && (metadata.uid() != info.uid || metadata.gid() != info.gid) {
+            info!(?hd_mount_path, "correcting ownership of home directory");
+            fix_ownership(&hd_mount_path, info.gid)?;
+            report.ownership_fixed = true;
+        }
+    }
 
-                // Create equivalence rule in the SELinux policy
-                #[cfg(all(target_family = "unix", feature = "selinux"))]
-                labeler.setup_equivalence_rule(&hd_mount_path)?;
+    if let Some(quota) = info.quota {
+        match apply_quota(info.uid, quota, &home_mount_prefix_path) {
+            Ok(()) => report.quota_applied = true,
+            Err(msg) => {
+                warn!(?quota, %msg, "Unable to apply home directory quota");
+                report.warnings.push(msg);
             }
         }
     }
@@ -266,7 +402,129 @@ fn create_home_directory(
             }
         }
     }
-    Ok(())
+    Ok(report)
+}
+
+fn remove_home_directory(
+    info: &HomeDirectoryInfo,
+    home_prefix_path: &Path,
+    home_mount_prefix_path: Option<&PathBuf>,
+) -> Result<(), String> {
+    // The same sanity checks as when the home directory was created.
+    let name = info.name.trim_start_matches('.').replace(['/', '\\'], "");
+
+    debug!(?home_prefix_path, ?home_mount_prefix_path, ?info);
+
+    let home_prefix_path = home_prefix_path
+        .canonicalize()
+        .map_err(|e| format!("{:?}", e))?;
+
+    let home_mount_prefix_path = home_mount_prefix_path
+        .unwrap_or(&home_prefix_path)
+        .canonicalize()
+        .map_err(|e| format!("{:?}", e))?;
+
+    if name.is_empty() {
+        return Err("Invalid home directory name - name is empty".to_string());
+    }
+
+    let hd_mount_path = Path::join(&home_mount_prefix_path, &name);
+
+    if hd_mount_path.parent() != Some(home_mount_prefix_path.as_path()) {
+        return Err("Invalid home directory name - not within home_mount_prefix".to_string());
+    }
+
+    // Remove the aliases first, so that nothing points to a directory that no longer exists.
+    for alias in info.aliases.iter() {
+        let alias = alias.trim_start_matches('.').replace(['/', '\\'], "");
+        let alias_path = Path::join(&home_prefix_path, &alias);
+
+        if alias_path.parent() != Some(home_prefix_path.as_path()) {
+            return Err("Invalid home directory alias - not within home_prefix".to_string());
+        }
+
+        match fs::symlink_metadata(&alias_path) {
+            Ok(attr) if attr.file_type().is_symlink() => {
+                debug!(?alias_path, "removing home directory alias");
+                if let Err(e) = fs::remove_file(&alias_path) {
+                    error!(err = ?e, ?alias_path, "Unable to remove alias path");
+                    return Err(format!("{:?}", e));
+                }
+            }
+            Ok(_) => {
+                warn!(
+                    ?alias_path,
+                    "home directory alias path is not a symlink, unable to remove"
+                );
+            }
+            Err(_) => {
+                // Already gone.
+            }
+        }
+    }
+
+    let metadata = match fs::symlink_metadata(&hd_mount_path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            debug!(?hd_mount_path, "home directory does not exist");
+            return Ok(());
+        }
+        Err(e) => {
+            error!(err = ?e, ?hd_mount_path, "Unable to read home directory metadata");
+            return Err(format!("{:?}", e));
+        }
+    };
+
+    // Only ever remove a real directory that belongs to this account. We never follow a
+    // symlink here, as that could be used to remove data outside of the home mount prefix.
+    if !metadata.file_type().is_dir() || metadata.uid() != info.uid {
+        warn!(
+            ?hd_mount_path,
+            "home directory is not a directory owned by the account, unable to remove"
+        );
+        return Err("Home directory is not a directory owned by the account".to_string());
+    }
+
+    info!(?hd_mount_path, "removing guest home directory");
+    fs::remove_dir_all(&hd_mount_path).map_err(|e| {
+        error!(err = ?e, ?hd_mount_path, "Unable to remove home directory");
+        format!("{:?}", e)
+    })
+}
+
+fn store_kerberos_credential_cache(info: &KerberosCredentialCacheInfo) -> Result<(), String> {
+    let ccache_path = PathBuf::from(format!("{}{}", KERBEROS_CCACHE_PREFIX, info.uid));
+    // The cache is written beside its final location and then moved into place, so that a
+    // file or symlink that another account planted at either path is replaced rather than
+    // written through.
+    let tmp_path = PathBuf::from(format!("{}{}.kanidm", KERBEROS_CCACHE_PREFIX, info.uid));
+
+    match fs::remove_file(&tmp_path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            error!(err = ?e, ?tmp_path, "Unable to remove stale credential cache");
+            return Err(format!("{:?}", e));
+        }
+    }
+
+    let write_ccache = || -> io::Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&tmp_path)?;
+        fchown(&file, Some(info.uid), Some(info.gid))?;
+        file.write_all(&info.ccache)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &ccache_path)
+    };
+
+    write_ccache().map_err(|e| {
+        error!(err = ?e, ?ccache_path, "Unable to store credential cache");
+        let _ = fs::remove_file(&tmp_path);
+        format!("{:?}", e)
+    })
 }
 
 async fn handle_tasks(stream: UnixStream, cfg: &UnixdConfig) {
@@ -281,10 +539,11 @@ async fn handle_tasks(stream: UnixStream, cfg: &UnixdConfig) {
                     &info,
                     cfg.home_prefix.as_ref(),
                     cfg.home_mount_prefix.as_ref(),
+                    cfg.home_skeleton_prefix.as_ref(),
                     cfg.use_etc_skel,
                     cfg.selinux,
                 ) {
-                    Ok(()) => TaskResponse::Success,
+                    Ok(report) => TaskResponse::HomeDirectory(report),
                     Err(msg) => TaskResponse::Error(msg),
                 };
 
@@ -295,6 +554,36 @@ async fn handle_tasks(stream: UnixStream, cfg: &UnixdConfig) {
                 }
                 // All good, loop.
             }
+            Some(Ok(TaskRequest::RemoveHomeDirectory(info))) => {
+                debug!("Received task -> RemoveHomeDirectory({:?})", info);
+
+                let resp = match remove_home_directory(
+                    &info,
+                    cfg.home_prefix.as_ref(),
+                    cfg.home_mount_prefix.as_ref(),
+                ) {
+                    Ok(()) => TaskResponse::Success,
+                    Err(msg) => TaskResponse::Error(msg),
+                };
+
+                if let Err(e) = reqs.send(resp).await {
+                    error!("Error -> {:?}", e);
+                    return;
+                }
+            }
+            Some(Ok(TaskRequest::KerberosCredentialCache(info))) => {
+                debug!("Received task -> KerberosCredentialCache({:?})", info);
+
+                let resp = match store_kerberos_credential_cache(&info) {
+                    Ok(()) => TaskResponse::Success,
+                    Err(msg) => TaskResponse::Error(msg),
+                };
+
+                if let Err(e) = reqs.send(resp).await {
+                    error!("Error -> {:?}", e);
+                    return;
+                }
+            }
             other => {
                 error!("Error -> {:?}", other);
                 return;
@@ -467,3 +756,271 @@ async fn main() -> ExitCode {
         })
         .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{quota_blocks, remove_home_directory, select_skeleton, setquota_command};
+    use kanidm_unix_common::unix_proto::HomeDirectoryInfo;
+    use kanidm_utils_users::get_effective_uid;
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::path::Path;
+
+    fn home_info(name: &str, aliases: &[&str]) -> HomeDirectoryInfo {
+        let uid = get_effective_uid();
+        HomeDirectoryInfo {
+            uid,
+            gid: uid,
+            name: name.to_string(),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            skeleton: None,
+            quota: None,
+        }
+    }
+
+    fn setup_home(home_prefix: &Path, name: &str) {
+        let hd_path = home_prefix.join(name);
+        fs::create_dir_all(hd_path.join(".config")).unwrap();
+        fs::write(hd_path.join(".config").join("settings"), "guest settings").unwrap();
+    }
+
+    #[test]
+    fn test_remove_home_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        let home_prefix = tmp.path();
+        setup_home(home_prefix, "guest_uuid");
+        symlink(home_prefix.join("guest_uuid"), home_prefix.join("guest")).unwrap();
+
+        let info = home_info("guest_uuid", &["guest"]);
+        remove_home_directory(&info, home_prefix, None).unwrap();
+
+        assert!(!home_prefix.join("guest_uuid").exists());
+        assert!(fs::symlink_metadata(home_prefix.join("guest")).is_err());
+
+        // Removing a home directory that is already gone succeeds.
+        remove_home_directory(&info, home_prefix, None).unwrap();
+    }
+
+    #[test]
+    fn test_remove_home_directory_mount_prefix() {
+        let home_prefix = tempfile::tempdir().unwrap();
+        let home_mount_prefix = tempfile::tempdir().unwrap();
+        setup_home(home_mount_prefix.path(), "guest_uuid");
+        symlink(
+            home_mount_prefix.path().join("guest_uuid"),
+            home_prefix.path().join("guest"),
+        )
+        .unwrap();
+
+        let info = home_info("guest_uuid", &["guest"]);
+        remove_home_directory(
+            &info,
+            home_prefix.path(),
+            Some(&home_mount_prefix.path().to_path_buf()),
+        )
+        .unwrap();
+
+        assert!(!home_mount_prefix.path().join("guest_uuid").exists());
+        assert!(fs::symlink_metadata(home_prefix.path().join("guest")).is_err());
+    }
+
+    #[test]
+    fn test_remove_home_directory_not_owned() {
+        let tmp = tempfile::tempdir().unwrap();
+        let home_prefix = tmp.path();
+        setup_home(home_prefix, "guest_uuid");
+
+        // A directory that belongs to another account is never removed.
+        let mut info = home_info("guest_uuid", &[]);
+        info.uid = info.uid.wrapping_add(1);
+        assert!(remove_home_directory(&info, home_prefix, None).is_err());
+        assert!(home_prefix.join("guest_uuid/.config/settings").exists());
+    }
+
+    #[test]
+    fn test_remove_home_directory_symlink() {
+        let tmp = tempfile::tempdir().unwrap();
+        let home_prefix = tmp.path().join("home");
+        fs::create_dir(&home_prefix).unwrap();
+        setup_home(tmp.path(), "elsewhere");
+
+        // The home directory is a symlink to data outside of the home prefix, which must
+        // not be followed.
+        symlink(tmp.path().join("elsewhere"), home_prefix.join("guest_uuid")).unwrap();
+
+        let info = home_info("guest_uuid", &[]);
+        assert!(remove_home_directory(&info, &home_prefix, None).is_err());
+        assert!(tmp.path().join("elsewhere/.config/settings").exists());
+    }
+
+    #[test]
+    fn test_remove_home_directory_sanitises_name() {
+        let tmp = tempfile::tempdir().unwrap();
+        let home_prefix = tmp.path().join("home");
+        fs::create_dir(&home_prefix).unwrap();
+        setup_home(tmp.path(), "outside");
+
+        // Path traversal is stripped from the name, so only the home prefix is searched.
+        for name in ["../outside", "..outside", "/outside", "out/side"] {
+            let info = home_info(name, &[]);
+            remove_home_directory(&info, &home_prefix, None).unwrap();
+            assert!(tmp.path().join("outside/.config/settings").exists());
+        }
+
+        // An alias that traverses out of the home prefix is also stripped.
+        let info = home_info("guest_uuid", &["../outside"]);
+        remove_home_directory(&info, &home_prefix, None).unwrap();
+        assert!(tmp.path().join("outside/.config/settings").exists());
+
+        let info = home_info("..", &[]);
+        assert!(remove_home_directory(&info, &home_prefix, None).is_err());
+    }
+
+    #[test]
+    fn test_select_skeleton() {
+        let tmp = tempfile::tempdir().unwrap();
+        let skel_prefix = tmp.path().join("skel");
+        let etc_skel = tmp.path().join("etc_skel");
+        fs::create_dir_all(skel_prefix.join("students")).unwrap();
+        fs::create_dir(&etc_skel).unwrap();
+
+        let mut warnings = Vec::new();
+        let (name, path) = select_skeleton(
+            Some("students"),
+            &skel_prefix,
+            &etc_skel,
+            true,
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(name, "students");
+        assert_eq!(path, skel_prefix.join("students"));
+        assert!(warnings.is_empty());
+
+        // Without a named skeleton, the system skeleton is used only if enabled.
+        let (name, path) =
+            select_skeleton(None, &skel_prefix, &etc_skel, true, &mut warnings).unwrap();
+        assert_eq!(name, etc_skel.display().to_string());
+        assert_eq!(path, etc_skel);
+        assert!(select_skeleton(None, &skel_prefix, &etc_skel, false, &mut warnings).is_none());
+        assert!(warnings.is_empty());
+
+        // Nor is it used if it doesn't exist.
+        let missing_etc_skel = tmp.path().join("missing");
+        assert!(
+            select_skeleton(None, &skel_prefix, &missing_etc_skel, true, &mut warnings).is_none()
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_select_skeleton_missing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let skel_prefix = tmp.path().join("skel");
+        let etc_skel = tmp.path().join("etc_skel");
+        fs::create_dir(&skel_prefix).unwrap();
+        fs::create_dir(&etc_skel).unwrap();
+
+        // A named skeleton that doesn't exist falls back to the system skeleton, with a
+        // warning so that the policy can be corrected.
+        let mut warnings = Vec::new();
+        let (_, path) =
+            select_skeleton(Some("staff"), &skel_prefix, &etc_skel, true, &mut warnings).unwrap();
+        assert_eq!(path, etc_skel);
+        assert_eq!(warnings, vec!["skeleton staff does not exist".to_string()]);
+
+        let mut warnings = Vec::new();
+        assert!(
+            select_skeleton(Some("staff"), &skel_prefix, &etc_skel, false, &mut warnings).is_none()
+        );
+        assert_eq!(warnings.len(), 1);
+
+        // A skeleton must be a directory.
+        fs::write(skel_prefix.join("file"), "not a skeleton").unwrap();
+        let mut warnings = Vec::new();
+        assert!(
+            select_skeleton(Some("file"), &skel_prefix, &etc_skel, false, &mut warnings).is_none()
+        );
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_select_skeleton_sanitises_name() {
+        let tmp = tempfile::tempdir().unwrap();
+        let skel_prefix = tmp.path().join("skel");
+        fs::create_dir_all(skel_prefix.join("students")).unwrap();
+        fs::create_dir_all(skel_prefix.join("ab")).unwrap();
+        // A directory beside the skeleton prefix that must never be used as a skeleton.
+        fs::create_dir_all(tmp.path().join("secret")).unwrap();
+        let etc_skel = tmp.path().join("missing");
+
+        // Path traversal is stripped from the name, so only the skeleton prefix is searched.
+        for name in [
+            "../secret",
+            "..secret",
+            "/secret",
+            "../../secret",
+            "\\secret",
+        ] {
+            let mut warnings = Vec::new();
+            assert!(
+                select_skeleton(Some(name), &skel_prefix, &etc_skel, false, &mut warnings)
+                    .is_none(),
+                "{name}"
+            );
+            assert_eq!(warnings, vec!["skeleton secret does not exist".to_string()]);
+        }
+
+        for name in ["..", ".", "/", ""] {
+            let mut warnings = Vec::new();
+            assert!(
+                select_skeleton(Some(name), &skel_prefix, &etc_skel, false, &mut warnings)
+                    .is_none(),
+                "{name}"
+            );
+            assert_eq!(warnings.len(), 1);
+        }
+
+        // Separators within the name are removed rather than followed.
+        let mut warnings = Vec::new();
+        let (name, path) =
+            select_skeleton(Some("a/b"), &skel_prefix, &etc_skel, false, &mut warnings).unwrap();
+        assert_eq!(name, "ab");
+        assert_eq!(path, skel_prefix.join("ab"));
+
+        let (name, path) = select_skeleton(
+            Some(".students"),
+            &skel_prefix,
+            &etc_skel,
+            false,
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(name, "students");
+        assert_eq!(path, skel_prefix.join("students"));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_quota_blocks() {
+        assert_eq!(quota_blocks(0), 0);
+        assert_eq!(quota_blocks(1), 1024);
+        assert_eq!(quota_blocks(5120), 5_242_880);
+        // The largest quota doesn't overflow.
+        assert_eq!(quota_blocks(u32::MAX), 4_398_046_510_080);
+    }
+
+    #[test]
+    fn test_setquota_command() {
+        let command = setquota_command(2000, 512, Path::new("/home"));
+        assert_eq!(command.get_program(), OsStr::new("setquota"));
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(
+            args,
+            ["-u", "2000", "524288", "524288", "0", "0", "/home"]
+                .map(OsStr::new)
+                .to_vec()
+        );
+    }
+}
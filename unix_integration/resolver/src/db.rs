@@ -936,6 +936,12 @@ mod tests {
             gidnumber: 2000,
             uuid: uuid::uuid!("0302b99c-f0f6-41ab-9492-852692b0fd16"),
             shell: None,
+            home_directory: None,
+            gecos: None,
+            home_skeleton: None,
+            home_quota: None,
+            hbac: None,
+            login_hosts: None,
             groups: Vec::new(),
             sshkeys: vec!["key-a".to_string()],
             valid: true,
@@ -1117,6 +1123,12 @@ mod tests {
             gidnumber: 2000,
             uuid: uuid::uuid!("0302b99c-f0f6-41ab-9492-852692b0fd16"),
             shell: None,
+            home_directory: None,
+            gecos: None,
+            home_skeleton: None,
+            home_quota: None,
+            hbac: None,
+            login_hosts: None,
             groups: vec![gt1.clone(), gt2],
             sshkeys: vec!["key-a".to_string()],
             valid: true,
@@ -1244,6 +1256,12 @@ mod tests {
             gidnumber: 2000,
             uuid: uuid::uuid!("0302b99c-f0f6-41ab-9492-852692b0fd16"),
             shell: None,
+            home_directory: None,
+            gecos: None,
+            home_skeleton: None,
+            home_quota: None,
+            hbac: None,
+            login_hosts: None,
             groups: Vec::new(),
             sshkeys: vec!["key-a".to_string()],
             valid: true,
@@ -1258,6 +1276,12 @@ mod tests {
             gidnumber: 2001,
             uuid: uuid::uuid!("799123b2-3802-4b19-b0b8-1ffae2aa9a4b"),
             shell: None,
+            home_directory: None,
+            gecos: None,
+            home_skeleton: None,
+            home_quota: None,
+            hbac: None,
+            login_hosts: None,
             groups: Vec::new(),
             sshkeys: vec!["key-a".to_string()],
             valid: true,
@@ -1,4 +1,6 @@
+use crate::db::KeyStoreTxn;
 use async_trait::async_trait;
+use kanidm_proto::v1::{KerberosKeytab, KerberosTicket, UnixHbacPolicy};
 use kanidm_unix_common::unix_proto::{
     DeviceAuthorizationResponse, PamAuthRequest, PamAuthResponse,
 };
@@ -104,6 +106,29 @@ pub struct GroupToken {
     pub extra_keys: BTreeMap<XKeyId, Value>,
 }
 
+/// The policy of a machine that is shared by many people, such as a kiosk or a lab
+/// workstation. This is distributed by the provider.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KioskPolicy {
+    /// How long, in seconds, accounts and groups may be cached for.
+    pub cache_timeout: u64,
+    /// Members of these groups are guests, whose home directories are removed when their
+    /// last session ends. Groups may be named by name, spn or uuid.
+    pub guest_groups: Vec<String>,
+}
+
+impl KioskPolicy {
+    pub fn is_guest(&self, token: &UserToken) -> bool {
+        token.groups.iter().any(|group| {
+            self.guest_groups.iter().any(|guest_group| {
+                guest_group == &group.name
+                    || guest_group == &group.spn
+                    || guest_group == &group.uuid.to_string()
+            })
+        })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserToken {
     #[serde(default)]
@@ -115,6 +140,22 @@ pub struct UserToken {
     pub gidnumber: u32,
     pub displayname: String,
     pub shell: Option<String>,
+    #[serde(default)]
+    pub home_directory: Option<String>,
+    #[serde(default)]
+    pub gecos: Option<String>,
+    #[serde(default)]
+    pub home_skeleton: Option<String>,
+    #[serde(default)]
+    pub home_quota: Option<u32>,
+    /// The host based access control rules from the provider. If `None`, access is
+    /// decided by the local configuration.
+    #[serde(default)]
+    pub hbac: Option<UnixHbacPolicy>,
+    /// The hosts that the user may log in to. If `None`, logins are not restricted to
+    /// specific hosts.
+    #[serde(default)]
+    pub login_hosts: Option<Vec<String>>,
     pub groups: Vec<GroupToken>,
 
     // Could there be a better type here?
@@ -215,22 +256,50 @@ pub trait IdProvider {
     /// Force this provider offline immediately.
     async fn mark_offline(&self);
 
-    /// Determine if this provider has a configured extension of a local system group
-    /// with remote members.
-    fn has_map_group(&self, local: &str) -> Option<&Id>;
+    /// The remote groups of this provider whose members extend the members of the local
+    /// system group `local`.
+    fn map_groups(&self, local: &str) -> &[Id];
 
-    // This is similar to a "domain join" process. What do we actually need to pass here
-    // for this to work for kanidm or himmelblau? Should we make it take a generic?
-    /*
+    /// Enroll this machine with the idp as the host `name`, exchanging the one time join token
+    /// for the credential that the provider authenticates to the idp with. This is similar to
+    /// a "domain join" process. Providers that have no concept of a machine identity reject
+    /// the request.
     async fn configure_machine_identity(
         &self,
+        _name: &str,
+        _join_token: &str,
         _keystore: &mut KeyStoreTxn,
-        _tpm: &mut tpm::BoxedDynTpm,
-        _machine_key: &tpm::MachineKey,
+        _now: SystemTime,
+    ) -> Result<(), IdpError> {
+        Err(IdpError::BadRequest)
+    }
+
+    /// Rotate the credential of this machine if it is due to be replaced. This is called
+    /// periodically by the resolver.
+    async fn refresh_machine_identity(
+        &self,
+        _keystore: &mut KeyStoreTxn,
+        _now: SystemTime,
     ) -> Result<(), IdpError> {
         Ok(())
     }
-    */
+
+    /// The kiosk policy of this machine, if the provider has marked it as a kiosk.
+    async fn kiosk_policy(&self) -> Option<KioskPolicy> {
+        None
+    }
+
+    /// Request the kerberos service tickets that are configured for an account that is
+    /// beginning a session. Tickets can only be issued while online, and providers that
+    /// have no kerberos bridge issue no tickets.
+    async fn kerberos_tickets(&self, _token: &UserToken) -> Result<Vec<KerberosTicket>, IdpError> {
+        Ok(Vec::new())
+    }
+
+    /// The keytab of the kerberos service principals of this machine.
+    async fn kerberos_keytab(&self) -> Result<KerberosKeytab, IdpError> {
+        Err(IdpError::BadRequest)
+    }
 
     async fn unix_user_get(
         &self,
@@ -296,7 +365,11 @@ pub trait IdProvider {
         _tpm: &mut tpm::BoxedDynTpm,
     ) -> Result<AuthResult, IdpError>;
 
-    async fn unix_user_authorise(&self, _token: &UserToken) -> Result<Option<bool>, IdpError>;
+    async fn unix_user_authorise(
+        &self,
+        _token: &UserToken,
+        _service: Option<&str>,
+    ) -> Result<Option<bool>, IdpError>;
 
     async fn unix_group_get(
         &self,
@@ -3,8 +3,13 @@ use crate::unix_config::{GroupMap, KanidmConfig};
 use async_trait::async_trait;
 use hashbrown::HashMap;
 use kanidm_client::{ClientError, KanidmClient, StatusCode};
+use kanidm_proto::constants::{
+    ATTR_HOST_KIOSK_CACHE_TIMEOUT, ATTR_HOST_KIOSK_GUEST_GROUP, ATTR_HOST_KIOSK_MODE,
+};
 use kanidm_proto::internal::OperationError;
-use kanidm_proto::v1::{UnixGroupToken, UnixUserToken};
+use kanidm_proto::v1::{Entry, KerberosKeytab, KerberosTicket, UnixGroupToken, UnixUserToken};
+use kanidm_unix_common::constants::DEFAULT_KIOSK_CACHE_TIMEOUT;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::time::{Duration, SystemTime};
 use tokio::sync::{broadcast, Mutex};
@@ -16,12 +21,19 @@ use kanidm_lib_crypto::Password;
 use super::interface::{
     tpm::{self, HmacKey, Tpm},
     AuthCredHandler, AuthRequest, AuthResult, GroupToken, GroupTokenState, Id, IdProvider,
-    IdpError, ProviderOrigin, UserToken, UserTokenState,
+    IdpError, KioskPolicy, ProviderOrigin, UserToken, UserTokenState,
 };
 use kanidm_unix_common::unix_proto::PamAuthRequest;
 
 const KANIDM_HMAC_KEY: &str = "kanidm-hmac-key";
 const KANIDM_PWV1_KEY: &str = "kanidm-pw-v1";
+const KANIDM_PINV1_KEY: &str = "kanidm-pin-v1";
+const KANIDM_HOST_CREDENTIAL: &str = "kanidm-host-credential";
+const KANIDM_KIOSK_POLICY: &str = "kanidm-kiosk-policy";
+
+// Host credentials are valid for 30 days, so we rotate them well before they expire in case
+// the machine is offline for some time.
+const HOST_CREDENTIAL_ROTATE_AFTER: Duration = Duration::from_secs(7 * 86400);
 
 // If the provider is offline, we need to backoff and wait a bit.
 const OFFLINE_NEXT_CHECK: Duration = Duration::from_secs(60);
@@ -33,19 +45,34 @@ enum CacheState {
     OfflineNextCheck(SystemTime),
 }
 
+/// The credential that this machine was issued when it enrolled as a host.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HostCredential {
+    name: String,
+    token: String,
+    issued_at: SystemTime,
+}
+
 struct KanidmProviderInternal {
     state: CacheState,
     client: KanidmClient,
     hmac_key: HmacKey,
     crypto_policy: CryptoPolicy,
+    /// If credentials are cached for offline authentication. They are only cached when the hsm
+    /// is a hardware tpm that can protect them from extraction.
+    cache_credentials: bool,
     pam_allow_groups: BTreeSet<String>,
+    hbac_host_name: Option<String>,
+    host_credential: Option<HostCredential>,
+    kiosk_policy: Option<KioskPolicy>,
+    kerberos_services: Vec<String>,
 }
 
 pub struct KanidmProvider {
     inner: Mutex<KanidmProviderInternal>,
     // Because this value doesn't change, to support fast
     // lookup we store the extension map here.
-    map_group: HashMap<String, Id>,
+    map_group: HashMap<String, Vec<Id>>,
 }
 
 impl KanidmProvider {
@@ -56,6 +83,7 @@ impl KanidmProvider {
         keystore: &mut KeyStoreTxn,
         tpm: &mut tpm::BoxedDynTpm,
         machine_key: &tpm::MachineKey,
+        cache_credentials: bool,
     ) -> Result<Self, IdpError> {
         // FUTURE: Randomised jitter on next check at startup.
 
@@ -96,12 +124,37 @@ impl KanidmProvider {
 
         let pam_allow_groups = config.pam_allowed_login_groups.iter().cloned().collect();
 
-        let map_group = config
-            .map_group
-            .iter()
-            .cloned()
-            .map(|GroupMap { local, with }| (local, Id::Name(with)))
-            .collect();
+        // If this machine has enrolled as a host, we authenticate with its credential rather
+        // than anonymously.
+        let host_credential: Option<HostCredential> = keystore
+            .get_tagged_hsm_key(KANIDM_HOST_CREDENTIAL)
+            .map_err(|ks_err| {
+                error!(?ks_err);
+                IdpError::KeyStore
+            })?;
+
+        // The kiosk policy is kept so that it still applies if we start while offline.
+        let kiosk_policy: Option<KioskPolicy> = keystore
+            .get_tagged_hsm_key(KANIDM_KIOSK_POLICY)
+            .map_err(|ks_err| {
+                error!(?ks_err);
+                IdpError::KeyStore
+            })?;
+
+        // The name that the machine enrolled as is the name that host based access control
+        // rules refer to, unless the configuration says otherwise.
+        let hbac_host_name = config
+            .hbac_host_name
+            .clone()
+            .or_else(|| host_credential.as_ref().map(|hc| hc.name.clone()))
+            .or_else(local_host_name);
+
+        // A local group may be extended by many remote groups, for example when more than
+        // one group should be granted wheel.
+        let mut map_group: HashMap<String, Vec<Id>> = HashMap::new();
+        for GroupMap { local, with } in config.map_group.iter().cloned() {
+            map_group.entry(local).or_default().push(Id::Name(with));
+        }
 
         Ok(KanidmProvider {
             inner: Mutex::new(KanidmProviderInternal {
@@ -109,13 +162,61 @@ impl KanidmProvider {
                 client,
                 hmac_key,
                 crypto_policy,
+                cache_credentials,
                 pam_allow_groups,
+                hbac_host_name,
+                host_credential,
+                kiosk_policy,
+                kerberos_services: config.kerberos_services.clone(),
             }),
             map_group,
         })
     }
 }
 
+/// The kiosk policy from the entry of this host, if it is a kiosk.
+fn kiosk_policy_from_entry(entry: &Entry) -> Option<KioskPolicy> {
+    let kiosk_mode = entry
+        .attrs
+        .get(ATTR_HOST_KIOSK_MODE)
+        .and_then(|values| values.first())
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+
+    if !kiosk_mode {
+        return None;
+    }
+
+    let cache_timeout = entry
+        .attrs
+        .get(ATTR_HOST_KIOSK_CACHE_TIMEOUT)
+        .and_then(|values| values.first())
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_KIOSK_CACHE_TIMEOUT);
+
+    let guest_groups = entry
+        .attrs
+        .get(ATTR_HOST_KIOSK_GUEST_GROUP)
+        .cloned()
+        .unwrap_or_default();
+
+    Some(KioskPolicy {
+        cache_timeout,
+        guest_groups,
+    })
+}
+
+/// Determine the name of this host from the system hostname.
+fn local_host_name() -> Option<String> {
+    let mut buf = [0u8; 256];
+    let r = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if r != 0 {
+        warn!("Unable to determine the system hostname");
+        return None;
+    }
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    String::from_utf8(buf[..len].to_vec()).ok()
+}
+
 impl From<UnixUserToken> for UserToken {
     fn from(value: UnixUserToken) -> UserToken {
         let UnixUserToken {
@@ -125,8 +226,14 @@ impl From<UnixUserToken> for UserToken {
             gidnumber,
             uuid,
             shell,
+            home_directory,
+            home_skeleton,
+            home_quota,
+            gecos,
             groups,
             sshkeys,
+            hbac,
+            login_hosts,
             valid,
         } = value;
 
@@ -142,6 +249,12 @@ impl From<UnixUserToken> for UserToken {
             gidnumber,
             displayname,
             shell,
+            home_directory,
+            home_skeleton,
+            home_quota,
+            hbac,
+            login_hosts,
+            gecos,
             groups,
             sshkeys,
             valid,
@@ -177,15 +290,59 @@ impl UserToken {
         cred: &str,
         tpm: &mut tpm::BoxedDynTpm,
         hmac_key: &HmacKey,
+    ) {
+        self.kanidm_update_sealed_credential(KANIDM_PWV1_KEY, crypto_policy, cred, tpm, hmac_key);
+        debug!(spn = %self.spn, "Updated cached pw");
+    }
+
+    pub fn kanidm_check_cached_password(
+        &self,
+        cred: &str,
+        tpm: &mut tpm::BoxedDynTpm,
+        hmac_key: &HmacKey,
+    ) -> bool {
+        self.kanidm_check_sealed_credential(KANIDM_PWV1_KEY, cred, tpm, hmac_key)
+    }
+
+    /// Store the Hello PIN of this account. It is sealed by the hsm in the same way as the
+    /// cached password, so that it can not be recovered from the cache either.
+    pub fn kanidm_update_cached_pin(
+        &mut self,
+        crypto_policy: &CryptoPolicy,
+        pin: &str,
+        tpm: &mut tpm::BoxedDynTpm,
+        hmac_key: &HmacKey,
+    ) {
+        self.kanidm_update_sealed_credential(KANIDM_PINV1_KEY, crypto_policy, pin, tpm, hmac_key);
+        debug!(spn = %self.spn, "Updated cached pin");
+    }
+
+    pub fn kanidm_check_cached_pin(
+        &self,
+        pin: &str,
+        tpm: &mut tpm::BoxedDynTpm,
+        hmac_key: &HmacKey,
+    ) -> bool {
+        self.kanidm_check_sealed_credential(KANIDM_PINV1_KEY, pin, tpm, hmac_key)
+    }
+
+    fn kanidm_update_sealed_credential(
+        &mut self,
+        key: &str,
+        crypto_policy: &CryptoPolicy,
+        cred: &str,
+        tpm: &mut tpm::BoxedDynTpm,
+        hmac_key: &HmacKey,
     ) {
         let pw = match Password::new_argon2id_hsm(crypto_policy, cred, tpm, hmac_key) {
             Ok(pw) => pw,
             Err(reason) => {
-                // Clear cached pw.
-                self.extra_keys.remove(KANIDM_PWV1_KEY);
+                // Clear cached credential.
+                self.extra_keys.remove(key);
                 warn!(
                     ?reason,
-                    "unable to apply kdf to password, clearing cached password."
+                    %key,
+                    "unable to apply kdf to credential, clearing cached credential."
                 );
                 return;
             }
@@ -194,30 +351,31 @@ impl UserToken {
         let pw_value = match serde_json::to_value(pw.to_dbpasswordv1()) {
             Ok(pw) => pw,
             Err(reason) => {
-                // Clear cached pw.
-                self.extra_keys.remove(KANIDM_PWV1_KEY);
+                // Clear cached credential.
+                self.extra_keys.remove(key);
                 warn!(
                     ?reason,
-                    "unable to serialise credential, clearing cached password."
+                    %key,
+                    "unable to serialise credential, clearing cached credential."
                 );
                 return;
             }
         };
 
-        self.extra_keys.insert(KANIDM_PWV1_KEY.into(), pw_value);
-        debug!(spn = %self.spn, "Updated cached pw");
+        self.extra_keys.insert(key.into(), pw_value);
     }
 
-    pub fn kanidm_check_cached_password(
+    fn kanidm_check_sealed_credential(
         &self,
+        key: &str,
         cred: &str,
         tpm: &mut tpm::BoxedDynTpm,
         hmac_key: &HmacKey,
     ) -> bool {
-        let pw_value = match self.extra_keys.get(KANIDM_PWV1_KEY) {
+        let pw_value = match self.extra_keys.get(key) {
             Some(pw_value) => pw_value,
             None => {
-                debug!(spn = %self.spn, "no cached pw available");
+                debug!(spn = %self.spn, %key, "no cached credential available");
                 return false;
             }
         };
@@ -225,7 +383,7 @@ impl UserToken {
         let dbpw = match serde_json::from_value::<DbPasswordV1>(pw_value.clone()) {
             Ok(dbpw) => dbpw,
             Err(reason) => {
-                warn!(spn = %self.spn, ?reason, "unable to deserialise credential");
+                warn!(spn = %self.spn, %key, ?reason, "unable to deserialise credential");
                 return false;
             }
         };
@@ -233,11 +391,20 @@ impl UserToken {
         let pw = match Password::try_from(dbpw) {
             Ok(pw) => pw,
             Err(reason) => {
-                warn!(spn = %self.spn, ?reason, "unable to process credential");
+                warn!(spn = %self.spn, %key, ?reason, "unable to process credential");
                 return false;
             }
         };
 
+        // Only trust credentials that were sealed by our hsm. Anything else was either cached by
+        // an older version, or was written into the cache by someone other than us, and could be
+        // verified or forged without access to the hsm. It will be replaced at the next online
+        // authentication.
+        if !pw.is_hsm_bound() {
+            warn!(spn = %self.spn, %key, "cached credential is not bound to the hsm, ignoring it");
+            return false;
+        }
+
         pw.verify_ctx(cred, Some((tpm, hmac_key)))
             .unwrap_or_default()
     }
@@ -257,13 +424,39 @@ impl KanidmProviderInternal {
         }
     }
 
+    /// Authenticate to the idp, either with the credential of this host if the machine has
+    /// enrolled, or anonymously. The kiosk policy of an enrolled host is updated from its entry.
+    async fn authenticate(&mut self) -> Result<(), ClientError> {
+        match &self.host_credential {
+            Some(host_credential) => {
+                self.client.set_token(host_credential.token.clone()).await;
+                // Check that the credential is still valid, as it may have been revoked.
+                match self.client.whoami().await? {
+                    Some(entry) => {
+                        self.kiosk_policy = kiosk_policy_from_entry(&entry);
+                        Ok(())
+                    }
+                    None => Err(ClientError::Http(
+                        StatusCode::UNAUTHORIZED,
+                        Some(OperationError::NotAuthenticated),
+                        String::default(),
+                    )),
+                }
+            }
+            None => {
+                self.kiosk_policy = None;
+                self.client.auth_anonymous().await
+            }
+        }
+    }
+
     #[instrument(level = "debug", skip_all)]
     async fn attempt_online(&mut self, _tpm: &mut tpm::BoxedDynTpm, now: SystemTime) -> bool {
         let mut max_attempts = 3;
         while max_attempts > 0 {
             max_attempts -= 1;
-            match self.client.auth_anonymous().await {
-                Ok(_uat) => {
+            match self.authenticate().await {
+                Ok(()) => {
                     debug!("provider is now online");
                     self.state = CacheState::Online;
                     return true;
@@ -305,8 +498,11 @@ impl IdProvider for KanidmProvider {
         inner.state = CacheState::OfflineNextCheck(now);
     }
 
-    fn has_map_group(&self, local: &str) -> Option<&Id> {
-        self.map_group.get(local)
+    fn map_groups(&self, local: &str) -> &[Id] {
+        self.map_group
+            .get(local)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
     }
 
     async fn mark_offline(&self) {
@@ -314,6 +510,206 @@ impl IdProvider for KanidmProvider {
         inner.state = CacheState::Offline;
     }
 
+    async fn kiosk_policy(&self) -> Option<KioskPolicy> {
+        let inner = self.inner.lock().await;
+        inner.kiosk_policy.clone()
+    }
+
+    async fn kerberos_tickets(&self, token: &UserToken) -> Result<Vec<KerberosTicket>, IdpError> {
+        let mut inner = self.inner.lock().await;
+
+        // Only an enrolled host may request tickets for the accounts that log in to it.
+        if inner.kerberos_services.is_empty() || inner.host_credential.is_none() {
+            return Ok(Vec::new());
+        }
+
+        if !matches!(inner.state, CacheState::Online) {
+            debug!("Provider is offline, unable to request kerberos tickets");
+            return Ok(Vec::new());
+        }
+
+        let services = inner.kerberos_services.clone();
+        let mut tickets = Vec::with_capacity(services.len());
+
+        for service in services.iter() {
+            // A service that can't be reached is no reason to deny the session, so the
+            // remaining tickets are still requested.
+            match inner
+                .client
+                .idm_host_kerberos_ticket(&token.spn, service)
+                .await
+            {
+                Ok(ticket) => tickets.push(ticket),
+                Err(ClientError::Transport(err)) => {
+                    error!(?err, "transport error");
+                    inner.state =
+                        CacheState::OfflineNextCheck(SystemTime::now() + OFFLINE_NEXT_CHECK);
+                    return Err(IdpError::Transport);
+                }
+                Err(err) => {
+                    warn!(?err, %service, spn = %token.spn, "Failed to request kerberos ticket");
+                }
+            }
+        }
+
+        Ok(tickets)
+    }
+
+    async fn kerberos_keytab(&self) -> Result<KerberosKeytab, IdpError> {
+        let inner = self.inner.lock().await;
+
+        if inner.host_credential.is_none() {
+            error!("This machine must be enrolled as a host to retrieve a kerberos keytab");
+            return Err(IdpError::BadRequest);
+        }
+
+        inner
+            .client
+            .idm_host_kerberos_keytab()
+            .await
+            .map_err(|err| {
+                error!(?err, "Failed to retrieve kerberos keytab");
+                match err {
+                    ClientError::Transport(_) => IdpError::Transport,
+                    ClientError::Http(StatusCode::UNAUTHORIZED, ..) => {
+                        IdpError::ProviderUnauthorised
+                    }
+                    _ => IdpError::BadRequest,
+                }
+            })
+    }
+
+    async fn configure_machine_identity(
+        &self,
+        name: &str,
+        join_token: &str,
+        keystore: &mut KeyStoreTxn,
+        now: SystemTime,
+    ) -> Result<(), IdpError> {
+        let mut inner = self.inner.lock().await;
+
+        let token = inner
+            .client
+            .idm_host_enroll(name, join_token)
+            .await
+            .map_err(|err| {
+                error!(?err, "Failed to enroll as host {}", name);
+                match err {
+                    ClientError::Transport(_) => IdpError::Transport,
+                    _ => IdpError::BadRequest,
+                }
+            })?;
+
+        let host_credential = HostCredential {
+            name: name.to_string(),
+            token,
+            issued_at: now,
+        };
+
+        keystore
+            .insert_tagged_hsm_key(KANIDM_HOST_CREDENTIAL, &host_credential)
+            .map_err(|ks_err| {
+                error!(?ks_err);
+                IdpError::KeyStore
+            })?;
+
+        info!("Enrolled as host {}", name);
+
+        inner.host_credential = Some(host_credential);
+        // Authenticate as the host as soon as possible.
+        inner.state = CacheState::OfflineNextCheck(now);
+
+        Ok(())
+    }
+
+    async fn refresh_machine_identity(
+        &self,
+        keystore: &mut KeyStoreTxn,
+        now: SystemTime,
+    ) -> Result<(), IdpError> {
+        let mut inner = self.inner.lock().await;
+
+        // Check the policy of this host for changes while we remain online.
+        if inner.host_credential.is_some() && matches!(inner.state, CacheState::Online) {
+            match inner.client.whoami().await {
+                Ok(Some(entry)) => inner.kiosk_policy = kiosk_policy_from_entry(&entry),
+                Ok(None) => {}
+                Err(err) => warn!(?err, "Failed to refresh the policy of this host"),
+            }
+        }
+
+        // Keep the latest kiosk policy, so that it still applies if we start while offline.
+        match inner.kiosk_policy.as_ref() {
+            Some(kiosk_policy) => keystore.insert_tagged_hsm_key(KANIDM_KIOSK_POLICY, kiosk_policy),
+            None => keystore.delete_tagged_hsm_key(KANIDM_KIOSK_POLICY),
+        }
+        .map_err(|ks_err| {
+            error!(?ks_err, "Failed to store kiosk policy");
+            IdpError::KeyStore
+        })?;
+
+        let Some(host_credential) = inner.host_credential.as_ref() else {
+            // Not enrolled, nothing to rotate.
+            return Ok(());
+        };
+
+        let rotate_at = host_credential.issued_at + HOST_CREDENTIAL_ROTATE_AFTER;
+        if now < rotate_at {
+            return Ok(());
+        }
+
+        let name = host_credential.name.clone();
+
+        if !matches!(inner.state, CacheState::Online) {
+            debug!("Provider is offline, deferring host credential rotation");
+            return Ok(());
+        }
+
+        let token = match inner.client.idm_host_rotate_credential().await {
+            Ok(token) => token,
+            Err(ClientError::Transport(err)) => {
+                error!(?err, "transport error");
+                inner.state = CacheState::OfflineNextCheck(now + OFFLINE_NEXT_CHECK);
+                return Err(IdpError::Transport);
+            }
+            Err(ClientError::Http(StatusCode::UNAUTHORIZED, reason, opid)) => {
+                error!(
+                    ?reason,
+                    ?opid,
+                    "Host credential was rejected during rotation"
+                );
+                inner.state = CacheState::OfflineNextCheck(now);
+                return Err(IdpError::ProviderUnauthorised);
+            }
+            Err(err) => {
+                error!(?err, "Failed to rotate host credential");
+                return Err(IdpError::BadRequest);
+            }
+        };
+
+        let host_credential = HostCredential {
+            name,
+            token,
+            issued_at: now,
+        };
+
+        // The previous credential is already revoked, so if we fail to store the new one we
+        // will need to be enrolled again.
+        keystore
+            .insert_tagged_hsm_key(KANIDM_HOST_CREDENTIAL, &host_credential)
+            .map_err(|ks_err| {
+                error!(?ks_err, "Failed to store rotated host credential");
+                IdpError::KeyStore
+            })?;
+
+        inner.client.set_token(host_credential.token.clone()).await;
+        inner.host_credential = Some(host_credential);
+
+        info!("Rotated host credential");
+
+        Ok(())
+    }
+
     async fn unix_user_get(
         &self,
         id: &Id,
@@ -452,12 +848,14 @@ impl IdProvider for KanidmProvider {
                 match auth_result {
                     Ok(Some(n_tok)) => {
                         let mut token = UserToken::from(n_tok);
-                        token.kanidm_update_cached_password(
-                            &inner.crypto_policy,
-                            cred.as_str(),
-                            tpm,
-                            &inner.hmac_key,
-                        );
+                        if inner.cache_credentials {
+                            token.kanidm_update_cached_password(
+                                &inner.crypto_policy,
+                                cred.as_str(),
+                                tpm,
+                                &inner.hmac_key,
+                            );
+                        }
 
                         Ok(AuthResult::Success { token })
                     }
@@ -563,6 +961,16 @@ impl IdProvider for KanidmProvider {
             (AuthCredHandler::Password, PamAuthRequest::Password { cred }) => {
                 let inner = self.inner.lock().await;
 
+                if !inner.cache_credentials {
+                    // A credential that was cached before may remain, but it is only protected
+                    // by the soft hsm so it can not be trusted.
+                    warn!(
+                        spn = %token.spn,
+                        "credentials are not cached without a hardware tpm, unable to authenticate offline"
+                    );
+                    return Ok(AuthResult::Denied);
+                }
+
                 if token.kanidm_check_cached_password(cred.as_str(), tpm, &inner.hmac_key) {
                     // TODO: We can update the token here and then do lockouts.
                     Ok(AuthResult::Success {
@@ -673,9 +1081,55 @@ impl IdProvider for KanidmProvider {
         }
     }
 
-    async fn unix_user_authorise(&self, token: &UserToken) -> Result<Option<bool>, IdpError> {
+    async fn unix_user_authorise(
+        &self,
+        token: &UserToken,
+        service: Option<&str>,
+    ) -> Result<Option<bool>, IdpError> {
         let inner = self.inner.lock().await;
 
+        // The account policy of the user may restrict them to hosts with certain tags, which
+        // applies in addition to any other access control.
+        if let Some(login_hosts) = &token.login_hosts {
+            let allowed = inner
+                .hbac_host_name
+                .as_deref()
+                .map(|host_name| {
+                    login_hosts
+                        .iter()
+                        .any(|h| h.eq_ignore_ascii_case(host_name))
+                })
+                .unwrap_or(false);
+
+            if !allowed {
+                debug!(
+                    host_name = ?inner.hbac_host_name,
+                    "Account policy does not allow the user to log in to this host"
+                );
+                return Ok(Some(false));
+            }
+        }
+
+        // When the server distributes host based access control rules, they replace the
+        // locally configured groups.
+        if let Some(hbac) = &token.hbac {
+            let Some(host_name) = inner.hbac_host_name.as_deref() else {
+                warn!("Cannot evaluate host based access control rules, unable to determine the name of this host!");
+                return Ok(Some(false));
+            };
+
+            let allowed = hbac.is_allowed(host_name, service);
+            debug!(
+                ?host_name,
+                ?service,
+                "Host based access control rules allow access: {}",
+                allowed
+            );
+            debug!("User token is valid: {}", token.valid);
+
+            return Ok(Some(allowed && token.valid));
+        }
+
         if inner.pam_allow_groups.is_empty() {
             // can't allow anything if the group list is zero...
             warn!("Cannot authenticate users, no allowed groups in configuration!");
@@ -699,3 +1153,150 @@ impl IdProvider for KanidmProvider {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{KanidmProvider, KANIDM_PWV1_KEY};
+    use crate::db::{Cache, Db};
+    use crate::idprovider::interface::{
+        AuthCredHandler, AuthResult, IdProvider, ProviderOrigin, UserToken,
+    };
+    use crate::unix_config::KanidmConfig;
+    use kanidm_client::KanidmClientBuilder;
+    use kanidm_hsm_crypto::{soft::SoftTpm, AuthValue, BoxedDynTpm, HmacKey, MachineKey, Tpm};
+    use kanidm_lib_crypto::{CryptoPolicy, Password};
+    use kanidm_unix_common::unix_proto::PamAuthRequest;
+    use std::time::SystemTime;
+
+    const TEST_PASSWORD: &str = "cached password for testuser";
+    const TEST_PIN: &str = "246810";
+
+    fn setup_hsm() -> (BoxedDynTpm, MachineKey, HmacKey) {
+        let mut tpm = BoxedDynTpm::new(SoftTpm::new());
+        let auth_value = AuthValue::ephemeral().unwrap();
+        let loadable_machine_key = tpm.machine_key_create(&auth_value).unwrap();
+        let machine_key = tpm
+            .machine_key_load(&auth_value, &loadable_machine_key)
+            .unwrap();
+        let loadable_hmac_key = tpm.hmac_key_create(&machine_key).unwrap();
+        let hmac_key = tpm.hmac_key_load(&machine_key, &loadable_hmac_key).unwrap();
+        (tpm, machine_key, hmac_key)
+    }
+
+    fn test_token() -> UserToken {
+        UserToken {
+            provider: ProviderOrigin::Kanidm,
+            name: "testuser".to_string(),
+            spn: "testuser@example.com".to_string(),
+            displayname: "Test User".to_string(),
+            gidnumber: 2000,
+            uuid: uuid::uuid!("0302b99c-f0f6-41ab-9492-852692b0fd16"),
+            shell: None,
+            home_directory: None,
+            gecos: None,
+            home_skeleton: None,
+            home_quota: None,
+            hbac: None,
+            login_hosts: None,
+            groups: Vec::new(),
+            sshkeys: Vec::new(),
+            valid: true,
+            extra_keys: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_cached_password_requires_hsm() {
+        sketching::test_init();
+        let (mut tpm, _machine_key, hmac_key) = setup_hsm();
+        let crypto_policy = CryptoPolicy::minimum();
+        let mut token = test_token();
+
+        // A credential that was sealed by the hsm is trusted.
+        token.kanidm_update_cached_password(&crypto_policy, TEST_PASSWORD, &mut tpm, &hmac_key);
+        assert!(token.kanidm_check_cached_password(TEST_PASSWORD, &mut tpm, &hmac_key));
+        assert!(!token.kanidm_check_cached_password("incorrect", &mut tpm, &hmac_key));
+
+        // Seed a credential that is not bound to the hsm, as an older version or someone
+        // with write access to the cache could have. Even with the correct password it
+        // must be refused.
+        let pw = Password::new_argon2id(&crypto_policy, TEST_PASSWORD).unwrap();
+        let pw_value = serde_json::to_value(pw.to_dbpasswordv1()).unwrap();
+        token.extra_keys.insert(KANIDM_PWV1_KEY.into(), pw_value);
+
+        assert!(!token.kanidm_check_cached_password(TEST_PASSWORD, &mut tpm, &hmac_key));
+    }
+
+    #[test]
+    fn test_cached_pin_is_sealed() {
+        sketching::test_init();
+        let (mut tpm, _machine_key, hmac_key) = setup_hsm();
+        let crypto_policy = CryptoPolicy::minimum();
+        let mut token = test_token();
+
+        assert!(!token.kanidm_check_cached_pin(TEST_PIN, &mut tpm, &hmac_key));
+
+        token.kanidm_update_cached_pin(&crypto_policy, TEST_PIN, &mut tpm, &hmac_key);
+        assert!(token.kanidm_check_cached_pin(TEST_PIN, &mut tpm, &hmac_key));
+        assert!(!token.kanidm_check_cached_pin("135790", &mut tpm, &hmac_key));
+
+        // The pin and the password are distinct credentials.
+        assert!(!token.kanidm_check_cached_password(TEST_PIN, &mut tpm, &hmac_key));
+
+        // The pin can only be verified with the hsm key that sealed it.
+        let (mut other_tpm, _other_machine_key, other_hmac_key) = setup_hsm();
+        assert!(!token.kanidm_check_cached_pin(TEST_PIN, &mut other_tpm, &other_hmac_key));
+    }
+
+    #[tokio::test]
+    async fn test_offline_auth_requires_hardware_tpm() {
+        sketching::test_init();
+        let (mut tpm, machine_key, hmac_key) = setup_hsm();
+        let crypto_policy = CryptoPolicy::minimum();
+
+        // A credential that was cached before the hardware tpm was removed.
+        let mut token = test_token();
+        token.kanidm_update_cached_password(&crypto_policy, TEST_PASSWORD, &mut tpm, &hmac_key);
+
+        let db = Db::new("").unwrap();
+        let mut dbtxn = db.write().await;
+        dbtxn.migrate().unwrap();
+
+        let client = KanidmClientBuilder::new()
+            .address("https://idm.example.com".to_string())
+            .build()
+            .unwrap();
+
+        let provider = KanidmProvider::new(
+            client,
+            &KanidmConfig {
+                conn_timeout: 1,
+                request_timeout: 1,
+                pam_allowed_login_groups: Vec::new(),
+                hbac_host_name: None,
+                kerberos_services: Vec::new(),
+                map_group: Vec::new(),
+            },
+            SystemTime::now(),
+            &mut (&mut dbtxn).into(),
+            &mut tpm,
+            &machine_key,
+            false,
+        )
+        .unwrap();
+
+        let result = provider
+            .unix_user_offline_auth_step(
+                &token,
+                &mut AuthCredHandler::Password,
+                PamAuthRequest::Password {
+                    cred: TEST_PASSWORD.to_string(),
+                },
+                &mut tpm,
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(result, AuthResult::Denied));
+    }
+}
@@ -33,6 +33,29 @@ pub enum KanidmUnixOpt {
         #[clap(short, long)]
         debug: bool,
     },
+    /// Enroll this machine with the kanidm server as the named host. The join token is issued
+    /// by an administrator with `kanidm system host generate-join-token`. Once enrolled the
+    /// unixd daemon authenticates as the host, and rotates the credential of the host
+    /// automatically.
+    Enroll {
+        #[clap(short, long)]
+        debug: bool,
+        /// The name of the host to enroll as.
+        name: String,
+        /// The join token that was issued for the host.
+        join_token: String,
+    },
+    /// Retrieve the keytab of the kerberos service principals of this host, so that services
+    /// such as NFS and SMB can accept the tickets that are issued to accounts. This machine
+    /// must be enrolled, and the service principals are set with
+    /// `kanidm system host add-kerberos-service-principal`.
+    KerberosKeytab {
+        #[clap(short, long)]
+        debug: bool,
+        /// The path to write the keytab to.
+        #[clap(short, long, default_value = "/etc/krb5.keytab")]
+        output: PathBuf,
+    },
     /// Show the version of this tool.
     Version {
         #[clap(short, long)]
@@ -22,6 +22,7 @@ use crate::idprovider::interface::{
     Id,
     IdProvider,
     IdpError,
+    KioskPolicy,
     ProviderOrigin,
     // KeyStore,
     UserToken,
@@ -34,11 +35,12 @@ use crate::unix_config::{HomeAttr, UidAttr};
 use kanidm_unix_common::constants::DEFAULT_SHELL_SEARCH_PATHS;
 use kanidm_unix_common::unix_passwd::{EtcGroup, EtcShadow, EtcUser};
 use kanidm_unix_common::unix_proto::{
-    HomeDirectoryInfo, NssGroup, NssUser, PamAuthRequest, PamAuthResponse, PamServiceInfo,
-    ProviderStatus,
+    HomeDirectoryInfo, KerberosCredentialCacheInfo, NssGroup, NssUser, PamAuthRequest,
+    PamAuthResponse, PamServiceInfo, ProviderStatus,
 };
 
 use kanidm_hsm_crypto::BoxedDynTpm;
+use kanidm_lib_crypto::kerberos::{kerberos_ccache, KerberosCredential, KerberosPrincipal};
 
 use tokio::sync::broadcast;
 
@@ -99,6 +101,10 @@ pub struct Resolver {
     uid_attr_map: UidAttr,
     gid_attr_map: UidAttr,
     nxcache: Mutex<LruCache<Id, SystemTime>>,
+
+    // The number of open sessions of each account, so that guest accounts on a kiosk
+    // can be cleaned up when their last session ends.
+    sessions: Mutex<HashMap<Uuid, usize>>,
 }
 
 impl Display for Id {
@@ -151,6 +157,7 @@ impl Resolver {
             uid_attr_map,
             gid_attr_map,
             nxcache: Mutex::new(LruCache::new(NXCACHE_SIZE)),
+            sessions: Mutex::new(HashMap::new()),
         })
     }
 
@@ -187,6 +194,62 @@ impl Resolver {
             .map_err(|_| ())
     }
 
+    /// Enroll this machine as a host with the primary provider.
+    #[instrument(level = "debug", skip_all)]
+    pub async fn configure_machine_identity(&self, name: &str, join_token: &str) -> Result<(), ()> {
+        let Some(client) = self.clients.first() else {
+            error!("No provider is configured to enroll with");
+            return Err(());
+        };
+
+        let now = SystemTime::now();
+        let mut dbtxn = self.db.write().await;
+
+        client
+            .configure_machine_identity(name, join_token, &mut (&mut dbtxn).into(), now)
+            .await
+            .map_err(|err| {
+                error!(?err, "Failed to configure machine identity");
+            })?;
+
+        dbtxn.commit().map_err(|_| ())
+    }
+
+    /// Rotate the machine credentials of any provider where they are due to be replaced.
+    #[instrument(level = "debug", skip_all)]
+    pub async fn refresh_machine_identity(&self) -> Result<(), ()> {
+        let now = SystemTime::now();
+        let mut dbtxn = self.db.write().await;
+
+        for client in self.clients.iter() {
+            if let Err(err) = client
+                .refresh_machine_identity(&mut (&mut dbtxn).into(), now)
+                .await
+            {
+                warn!(?err, origin = %client.origin(), "Failed to refresh machine identity");
+            }
+        }
+
+        dbtxn.commit().map_err(|_| ())
+    }
+
+    /// The kiosk policy of the primary provider, if this machine is a kiosk.
+    async fn kiosk_policy(&self) -> Option<KioskPolicy> {
+        match self.client_ids.get(&self.primary_origin) {
+            Some(client) => client.kiosk_policy().await,
+            None => None,
+        }
+    }
+
+    /// How long entries are cached for. A kiosk may ask for a shorter time than the
+    /// configuration, so that changes to accounts apply quickly on shared machines.
+    async fn cache_timeout_seconds(&self) -> u64 {
+        match self.kiosk_policy().await {
+            Some(policy) => self.timeout_seconds.min(policy.cache_timeout),
+            None => self.timeout_seconds,
+        }
+    }
+
     async fn get_cached_usertokens(&self) -> Result<Vec<UserToken>, ()> {
         let mut dbtxn = self.db.write().await;
         dbtxn.get_accounts().map_err(|_| ())
@@ -321,7 +384,7 @@ impl Resolver {
 
     async fn set_cache_usertoken(&self, token: &mut UserToken) -> Result<(), ()> {
         // Set an expiry
-        let ex_time = SystemTime::now() + Duration::from_secs(self.timeout_seconds);
+        let ex_time = SystemTime::now() + Duration::from_secs(self.cache_timeout_seconds().await);
         let offset = ex_time
             .duration_since(SystemTime::UNIX_EPOCH)
             .map_err(|e| {
@@ -412,7 +475,7 @@ impl Resolver {
 
     async fn set_cache_grouptoken(&self, token: &GroupToken) -> Result<(), ()> {
         // Set an expiry
-        let ex_time = SystemTime::now() + Duration::from_secs(self.timeout_seconds);
+        let ex_time = SystemTime::now() + Duration::from_secs(self.cache_timeout_seconds().await);
         let offset = ex_time
             .duration_since(SystemTime::UNIX_EPOCH)
             .map_err(|e| {
@@ -650,6 +713,16 @@ impl Resolver {
     }
 
     fn token_homedirectory_alias(&self, token: &UserToken) -> Option<String> {
+        // A home directory from the account policy of the provider takes precedence. It must
+        // be a single path component, so that it can't escape the home prefix.
+        if let Some(home_directory) = token
+            .home_directory
+            .as_deref()
+            .filter(|h| !h.is_empty() && *h != "." && *h != ".." && !h.contains(['/', '\0']))
+        {
+            return Some(home_directory.to_string());
+        }
+
         let is_primary_origin = token.provider == self.primary_origin;
         self.home_alias.map(|t| match t {
             // If we have an alias. use it.
@@ -704,7 +777,7 @@ impl Resolver {
                 name: self.token_uidattr(&tok),
                 uid: tok.gidnumber,
                 gid: tok.gidnumber,
-                gecos: tok.displayname,
+                gecos: tok.gecos.unwrap_or(tok.displayname),
                 shell: tok.shell.unwrap_or_else(|| self.default_shell.clone()),
             }))
             .collect())
@@ -723,7 +796,7 @@ impl Resolver {
             name: self.token_uidattr(&tok),
             uid: tok.gidnumber,
             gid: tok.gidnumber,
-            gecos: tok.displayname,
+            gecos: tok.gecos.unwrap_or(tok.displayname),
             shell: tok.shell.unwrap_or_else(|| self.default_shell.clone()),
         }))
     }
@@ -753,7 +826,7 @@ impl Resolver {
         // Extend all the local groups if maps exist.
         for nss_group in r.iter_mut() {
             for client in self.clients.iter() {
-                if let Some(extend_group_id) = client.has_map_group(&nss_group.name) {
+                for extend_group_id in client.map_groups(&nss_group.name) {
                     let (_, token) = self.get_cached_grouptoken(extend_group_id).await?;
                     if let Some(token) = token {
                         let members = self.get_groupmembers(token.uuid).await;
@@ -765,6 +838,9 @@ impl Resolver {
                     }
                 }
             }
+
+            nss_group.members.sort_unstable();
+            nss_group.members.dedup();
         }
 
         let l = self.get_cached_grouptokens().await?;
@@ -785,7 +861,7 @@ impl Resolver {
             debug!("system provider satisfied request");
 
             for client in self.clients.iter() {
-                if let Some(extend_group_id) = client.has_map_group(&nss_group.name) {
+                for extend_group_id in client.map_groups(&nss_group.name) {
                     let token = self.get_grouptoken(extend_group_id.clone()).await?;
                     if let Some(token) = token {
                         let members = self.get_groupmembers(token.uuid).await;
@@ -830,7 +906,11 @@ impl Resolver {
     }
 
     #[instrument(level = "debug", skip(self))]
-    pub async fn pam_account_allowed(&self, account_id: &str) -> Result<Option<bool>, ()> {
+    pub async fn pam_account_allowed(
+        &self,
+        account_id: &str,
+        service: Option<&str>,
+    ) -> Result<Option<bool>, ()> {
         let id = Id::Name(account_id.to_string());
 
         if let Some(answer) = self.system_provider.authorise(&id).await {
@@ -849,9 +929,12 @@ impl Resolver {
                         error!(provider = ?token.provider, "Token was resolved by a provider that no longer appears to be present.");
                     })?;
 
-                client.unix_user_authorise(&token).await.map_err(|err| {
-                    error!(?err, "unable to authorise account");
-                })
+                client
+                    .unix_user_authorise(&token, service)
+                    .await
+                    .map_err(|err| {
+                        error!(?err, "unable to authorise account");
+                    })
             }
             None => Ok(None),
         }
@@ -970,6 +1053,14 @@ impl Resolver {
                         Err(())
                     }
                 }
+            } else if client.kiosk_policy().await.is_some() {
+                // Kiosks always require the idp to confirm the credentials, so that a locked
+                // session can't be unlocked with a credential that has since been revoked.
+                warn!(
+                    ?account_id,
+                    "Denying offline auth request as this machine is a kiosk"
+                );
+                Ok((AuthSession::Denied, PamAuthResponse::Denied))
             } else {
                 // Can the auth proceed offline?
                 let init_result = client.unix_user_offline_auth_init(&token).await;
@@ -1282,15 +1373,157 @@ impl Resolver {
 
         // Not a system account, check based on the token and resolve.
         let token = self.get_usertoken(&id).await?;
-        Ok(token.as_ref().map(|tok| HomeDirectoryInfo {
+
+        if let Some(tok) = token.as_ref() {
+            let mut sessions = self.sessions.lock().await;
+            *sessions.entry(tok.uuid).or_default() += 1;
+        }
+
+        Ok(token.as_ref().map(|tok| self.token_homedirectory_info(tok)))
+    }
+
+    /// End a session of an account. If this was the last session of a guest account on a
+    /// kiosk, the home directory that should be removed is returned.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn pam_account_endsession(
+        &self,
+        account_id: &str,
+    ) -> Result<Option<HomeDirectoryInfo>, ()> {
+        let id = Id::Name(account_id.to_string());
+
+        if let SystemProviderSession::Start = self.system_provider.begin_session(&id).await {
+            return Ok(None);
+        }
+
+        let Some(token) = self.get_usertoken(&id).await? else {
+            return Ok(None);
+        };
+
+        let last_session = {
+            let mut sessions = self.sessions.lock().await;
+            match sessions.get_mut(&token.uuid) {
+                Some(count) if *count > 1 => {
+                    *count -= 1;
+                    false
+                }
+                Some(_) => {
+                    sessions.remove(&token.uuid);
+                    true
+                }
+                // We never saw this session begin, so we can't know if it was the last.
+                None => false,
+            }
+        };
+
+        if !last_session {
+            return Ok(None);
+        }
+
+        let is_guest = self
+            .kiosk_policy()
+            .await
+            .map(|policy| policy.is_guest(&token))
+            .unwrap_or_default();
+
+        if is_guest {
+            info!(?account_id, "last session of kiosk guest ended");
+            Ok(Some(self.token_homedirectory_info(&token)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Request the kerberos tickets of an account that is beginning a session, and assemble
+    /// them into a credential cache. If no tickets were issued, `None` is returned.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn kerberos_credential_cache(
+        &self,
+        account_id: &str,
+    ) -> Result<Option<KerberosCredentialCacheInfo>, ()> {
+        let id = Id::Name(account_id.to_string());
+
+        if let SystemProviderSession::Start = self.system_provider.begin_session(&id).await {
+            return Ok(None);
+        }
+
+        let Some(token) = self.get_usertoken(&id).await? else {
+            return Ok(None);
+        };
+
+        let Some(client) = self.client_ids.get(&token.provider) else {
+            error!(provider = ?token.provider, "Token was resolved by a provider that no longer appears to be present.");
+            return Ok(None);
+        };
+
+        let tickets = client.kerberos_tickets(&token).await.map_err(|err| {
+            error!(?err, "Failed to request kerberos tickets");
+        })?;
+
+        let credentials: Vec<_> = tickets
+            .into_iter()
+            .filter_map(|ticket| {
+                let (name, realm) = ticket.client.rsplit_once('@')?;
+                let client = KerberosPrincipal::user(name, realm);
+                let (service, _) = ticket.service.rsplit_once('@')?;
+                let service = KerberosPrincipal::service(service, &ticket.realm)?;
+
+                Some(KerberosCredential {
+                    client,
+                    service,
+                    session_key: ticket.session_key,
+                    auth_time: ticket.auth_time,
+                    end_time: ticket.end_time,
+                    flags: 0,
+                    ticket: ticket.ticket,
+                })
+            })
+            .collect();
+
+        if credentials.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(KerberosCredentialCacheInfo {
+            uid: token.gidnumber,
+            gid: token.gidnumber,
+            ccache: kerberos_ccache(&credentials),
+        }))
+    }
+
+    /// The keytab of the kerberos service principals of this machine, from the primary
+    /// provider.
+    pub async fn kerberos_keytab(&self) -> Result<Vec<u8>, ()> {
+        let Some(client) = self.client_ids.get(&self.primary_origin) else {
+            error!("No provider is configured to retrieve a kerberos keytab from");
+            return Err(());
+        };
+
+        let keytab = client.kerberos_keytab().await.map_err(|err| {
+            error!(?err, "Failed to retrieve kerberos keytab");
+        })?;
+
+        info!(principals = ?keytab.principals, "Retrieved kerberos keytab");
+
+        Ok(keytab.keytab)
+    }
+
+    fn token_homedirectory_info(&self, tok: &UserToken) -> HomeDirectoryInfo {
+        let name = self.token_homedirectory_attr(tok);
+        // The alias may be the same as the home directory itself.
+        let aliases = self
+            .token_homedirectory_alias(tok)
+            .filter(|alias| *alias != name)
+            .map(|s| vec![s])
+            .unwrap_or_default();
+
+        HomeDirectoryInfo {
             uid: tok.gidnumber,
             gid: tok.gidnumber,
-            name: self.token_homedirectory_attr(tok),
-            aliases: self
-                .token_homedirectory_alias(tok)
-                .map(|s| vec![s])
-                .unwrap_or_default(),
-        }))
+            name,
+            aliases,
+            skeleton: tok.home_skeleton.clone(),
+            quota: tok.home_quota,
+        }
     }
 
     pub async fn provider_status(&self) -> Vec<ProviderStatus> {
@@ -1332,3 +1565,386 @@ impl Resolver {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthSession, Resolver};
+    use crate::db::{Cache, Db};
+    use crate::idprovider::interface::{
+        tpm, AuthCredHandler, AuthRequest, AuthResult, GroupToken, GroupTokenState, Id, IdProvider,
+        IdpError, KioskPolicy, ProviderOrigin, UserToken, UserTokenState,
+    };
+    use crate::idprovider::system::SystemProvider;
+    use crate::unix_config::{HomeAttr, UidAttr};
+    use async_trait::async_trait;
+    use kanidm_hsm_crypto::{soft::SoftTpm, BoxedDynTpm};
+    use kanidm_unix_common::unix_proto::{PamAuthRequest, PamAuthResponse, PamServiceInfo};
+    use std::sync::Arc;
+    use std::time::SystemTime;
+    use time::OffsetDateTime;
+    use tokio::sync::broadcast;
+
+    const GUEST_GROUP: &str = "kiosk_guests";
+
+    /// A provider that is always offline, and that knows a fixed set of accounts.
+    struct TestProvider {
+        kiosk_policy: Option<KioskPolicy>,
+        users: Vec<UserToken>,
+    }
+
+    #[async_trait]
+    impl IdProvider for TestProvider {
+        fn origin(&self) -> ProviderOrigin {
+            ProviderOrigin::Kanidm
+        }
+
+        async fn attempt_online(&self, _tpm: &mut tpm::BoxedDynTpm, _now: SystemTime) -> bool {
+            false
+        }
+
+        async fn mark_next_check(&self, _now: SystemTime) {}
+
+        async fn mark_offline(&self) {}
+
+        fn map_groups(&self, _local: &str) -> &[Id] {
+            &[]
+        }
+
+        async fn kiosk_policy(&self) -> Option<KioskPolicy> {
+            self.kiosk_policy.clone()
+        }
+
+        async fn unix_user_get(
+            &self,
+            id: &Id,
+            _token: Option<&UserToken>,
+            _tpm: &mut tpm::BoxedDynTpm,
+            _now: SystemTime,
+        ) -> Result<UserTokenState, IdpError> {
+            let Id::Name(name) = id else {
+                return Ok(UserTokenState::NotFound);
+            };
+
+            Ok(self
+                .users
+                .iter()
+                .find(|token| &token.name == name)
+                .cloned()
+                .map(UserTokenState::Update)
+                .unwrap_or(UserTokenState::NotFound))
+        }
+
+        async fn unix_user_online_auth_init(
+            &self,
+            _account_id: &str,
+            _token: &UserToken,
+            _tpm: &mut tpm::BoxedDynTpm,
+            _shutdown_rx: &broadcast::Receiver<()>,
+        ) -> Result<(AuthRequest, AuthCredHandler), IdpError> {
+            Err(IdpError::BadRequest)
+        }
+
+        async fn unix_user_online_auth_step(
+            &self,
+            _account_id: &str,
+            _cred_handler: &mut AuthCredHandler,
+            _pam_next_req: PamAuthRequest,
+            _tpm: &mut tpm::BoxedDynTpm,
+            _shutdown_rx: &broadcast::Receiver<()>,
+        ) -> Result<AuthResult, IdpError> {
+            Err(IdpError::BadRequest)
+        }
+
+        async fn unix_unknown_user_online_auth_init(
+            &self,
+            _account_id: &str,
+            _tpm: &mut tpm::BoxedDynTpm,
+            _shutdown_rx: &broadcast::Receiver<()>,
+        ) -> Result<Option<(AuthRequest, AuthCredHandler)>, IdpError> {
+            Ok(None)
+        }
+
+        async fn unix_user_offline_auth_init(
+            &self,
+            _token: &UserToken,
+        ) -> Result<(AuthRequest, AuthCredHandler), IdpError> {
+            Ok((AuthRequest::Password, AuthCredHandler::Password))
+        }
+
+        async fn unix_user_offline_auth_step(
+            &self,
+            _token: &UserToken,
+            _cred_handler: &mut AuthCredHandler,
+            _pam_next_req: PamAuthRequest,
+            _tpm: &mut tpm::BoxedDynTpm,
+        ) -> Result<AuthResult, IdpError> {
+            Ok(AuthResult::Denied)
+        }
+
+        async fn unix_user_authorise(
+            &self,
+            _token: &UserToken,
+            _service: Option<&str>,
+        ) -> Result<Option<bool>, IdpError> {
+            Ok(Some(true))
+        }
+
+        async fn unix_group_get(
+            &self,
+            _id: &Id,
+            _tpm: &mut tpm::BoxedDynTpm,
+            _now: SystemTime,
+        ) -> Result<GroupTokenState, IdpError> {
+            Ok(GroupTokenState::NotFound)
+        }
+    }
+
+    fn test_user(name: &str, gidnumber: u32, guest: bool) -> UserToken {
+        let groups = if guest {
+            vec![GroupToken {
+                provider: ProviderOrigin::Kanidm,
+                name: GUEST_GROUP.to_string(),
+                spn: format!("{GUEST_GROUP}@example.com"),
+                uuid: uuid::uuid!("8f3a9c1e-5b0d-4e2a-9d7c-1f6e2b4a8c30"),
+                gidnumber: 30000,
+                extra_keys: Default::default(),
+            }]
+        } else {
+            Vec::new()
+        };
+
+        UserToken {
+            provider: ProviderOrigin::Kanidm,
+            name: name.to_string(),
+            spn: format!("{name}@example.com"),
+            uuid: uuid::Uuid::new_v4(),
+            gidnumber,
+            displayname: name.to_string(),
+            shell: None,
+            home_directory: None,
+            gecos: None,
+            home_skeleton: None,
+            home_quota: None,
+            hbac: None,
+            login_hosts: None,
+            groups,
+            sshkeys: Vec::new(),
+            valid: true,
+            extra_keys: Default::default(),
+        }
+    }
+
+    async fn setup_resolver(kiosk_policy: Option<KioskPolicy>) -> Resolver {
+        sketching::test_init();
+
+        let db = Db::new("").expect("Failed to setup DB");
+        {
+            let mut dbtxn = db.write().await;
+            dbtxn.migrate().expect("Unable to migrate cache db");
+            dbtxn.commit().expect("Unable to commit dbtxn");
+        }
+
+        let provider = TestProvider {
+            kiosk_policy,
+            users: vec![
+                test_user("guest", 20000, true),
+                test_user("staff", 20001, false),
+            ],
+        };
+
+        Resolver::new(
+            db,
+            Arc::new(SystemProvider::new().expect("Failed to setup system provider")),
+            vec![Arc::new(provider)],
+            BoxedDynTpm::new(SoftTpm::new()),
+            300,
+            "/bin/sh".to_string(),
+            "/home/".into(),
+            HomeAttr::Uuid,
+            None,
+            UidAttr::Spn,
+            UidAttr::Spn,
+        )
+        .await
+        .expect("Failed to setup resolver")
+    }
+
+    fn kiosk_policy() -> KioskPolicy {
+        KioskPolicy {
+            cache_timeout: 60,
+            guest_groups: vec![GUEST_GROUP.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_kiosk_policy_is_guest() {
+        let guest = test_user("guest", 20000, true);
+        let staff = test_user("staff", 20001, false);
+        let group = &guest.groups[0];
+
+        // Guest groups may be named by name, spn or uuid.
+        for guest_group in [
+            group.name.clone(),
+            group.spn.clone(),
+            group.uuid.to_string(),
+        ] {
+            let policy = KioskPolicy {
+                cache_timeout: 60,
+                guest_groups: vec![guest_group],
+            };
+            assert!(policy.is_guest(&guest));
+            assert!(!policy.is_guest(&staff));
+        }
+
+        let policy = KioskPolicy {
+            cache_timeout: 60,
+            guest_groups: Vec::new(),
+        };
+        assert!(!policy.is_guest(&guest));
+    }
+
+    #[tokio::test]
+    async fn test_kiosk_cache_timeout() {
+        let resolver = setup_resolver(None).await;
+        assert_eq!(resolver.cache_timeout_seconds().await, 300);
+
+        // A kiosk may only shorten how long entries are cached for.
+        let resolver = setup_resolver(Some(kiosk_policy())).await;
+        assert_eq!(resolver.cache_timeout_seconds().await, 60);
+
+        let resolver = setup_resolver(Some(KioskPolicy {
+            cache_timeout: 3600,
+            guest_groups: Vec::new(),
+        }))
+        .await;
+        assert_eq!(resolver.cache_timeout_seconds().await, 300);
+    }
+
+    #[tokio::test]
+    async fn test_kiosk_guest_session_cleanup() {
+        let resolver = setup_resolver(Some(kiosk_policy())).await;
+
+        // Every session is given the home directory to prepare.
+        let info = resolver
+            .pam_account_beginsession("guest")
+            .await
+            .expect("Failed to begin session")
+            .expect("No home directory for guest");
+        assert_eq!(info.uid, 20000);
+        assert_eq!(info.gid, 20000);
+        assert!(resolver
+            .pam_account_beginsession("guest")
+            .await
+            .expect("Failed to begin session")
+            .is_some());
+
+        // The home directory is kept while the guest still has a session.
+        assert!(resolver
+            .pam_account_endsession("guest")
+            .await
+            .expect("Failed to end session")
+            .is_none());
+
+        // And removed when their last session ends.
+        let removed = resolver
+            .pam_account_endsession("guest")
+            .await
+            .expect("Failed to end session")
+            .expect("Guest home directory was not removed");
+        assert_eq!(removed.name, info.name);
+        assert_eq!(removed.uid, info.uid);
+
+        // Ending a session that was never begun can't remove anything, as we don't know
+        // if other sessions are open.
+        assert!(resolver
+            .pam_account_endsession("guest")
+            .await
+            .expect("Failed to end session")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_kiosk_non_guest_session_kept() {
+        let resolver = setup_resolver(Some(kiosk_policy())).await;
+
+        // Accounts that aren't guests keep their home directory on a kiosk.
+        assert!(resolver
+            .pam_account_beginsession("staff")
+            .await
+            .expect("Failed to begin session")
+            .is_some());
+        assert!(resolver
+            .pam_account_endsession("staff")
+            .await
+            .expect("Failed to end session")
+            .is_none());
+
+        // Accounts that don't exist have no sessions to end.
+        assert!(resolver
+            .pam_account_beginsession("nobody")
+            .await
+            .expect("Failed to begin session")
+            .is_none());
+        assert!(resolver
+            .pam_account_endsession("nobody")
+            .await
+            .expect("Failed to end session")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_non_kiosk_guest_session_kept() {
+        // Without a kiosk policy, no home directory is ever removed.
+        let resolver = setup_resolver(None).await;
+
+        assert!(resolver
+            .pam_account_beginsession("guest")
+            .await
+            .expect("Failed to begin session")
+            .is_some());
+        assert!(resolver
+            .pam_account_endsession("guest")
+            .await
+            .expect("Failed to end session")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_kiosk_denies_offline_auth() {
+        let pam_info = PamServiceInfo {
+            service: "login".to_string(),
+            tty: None,
+            rhost: None,
+        };
+
+        // An offline machine that isn't a kiosk may authenticate with cached credentials.
+        let resolver = setup_resolver(None).await;
+        let (_tx, shutdown_rx) = broadcast::channel(1);
+        let (session, response) = resolver
+            .pam_account_authenticate_init(
+                "staff",
+                &pam_info,
+                OffsetDateTime::now_utc(),
+                shutdown_rx,
+            )
+            .await
+            .expect("Failed to begin authentication");
+        assert!(matches!(session, AuthSession::Offline { .. }));
+        assert!(matches!(response, PamAuthResponse::Password));
+
+        // A kiosk refuses, as the idp must confirm the credentials.
+        let resolver = setup_resolver(Some(kiosk_policy())).await;
+        let (_tx, shutdown_rx) = broadcast::channel(1);
+        let (session, response) = resolver
+            .pam_account_authenticate_init(
+                "staff",
+                &pam_info,
+                OffsetDateTime::now_utc(),
+                shutdown_rx,
+            )
+            .await
+            .expect("Failed to begin authentication");
+        assert!(matches!(session, AuthSession::Denied));
+        assert!(matches!(response, PamAuthResponse::Denied));
+    }
+}
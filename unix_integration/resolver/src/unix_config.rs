@@ -49,6 +49,7 @@ struct ConfigV2 {
     home_attr: Option<String>,
     home_alias: Option<String>,
     use_etc_skel: Option<bool>,
+    home_skeleton_prefix: Option<String>,
     uid_attr_map: Option<String>,
     gid_attr_map: Option<String>,
     selinux: Option<bool>,
@@ -71,6 +72,9 @@ struct KanidmConfigV2 {
     conn_timeout: Option<u64>,
     request_timeout: Option<u64>,
     pam_allowed_login_groups: Option<Vec<String>>,
+    hbac_host_name: Option<String>,
+    #[serde(default)]
+    kerberos_services: Vec<String>,
     #[serde(default)]
     map_group: Vec<GroupMap>,
 }
@@ -90,6 +94,7 @@ struct ConfigInt {
     home_attr: Option<String>,
     home_alias: Option<String>,
     use_etc_skel: Option<bool>,
+    home_skeleton_prefix: Option<String>,
     uid_attr_map: Option<String>,
     gid_attr_map: Option<String>,
     selinux: Option<bool>,
@@ -139,6 +144,7 @@ pub struct UnixdConfig {
     pub home_attr: HomeAttr,
     pub home_alias: Option<HomeAttr>,
     pub use_etc_skel: bool,
+    pub home_skeleton_prefix: PathBuf,
     pub uid_attr_map: UidAttr,
     pub gid_attr_map: UidAttr,
     pub selinux: bool,
@@ -154,6 +160,12 @@ pub struct KanidmConfig {
     pub conn_timeout: u64,
     pub request_timeout: u64,
     pub pam_allowed_login_groups: Vec<String>,
+    /// The name of this host in host based access control rules. Defaults to the
+    /// system hostname.
+    pub hbac_host_name: Option<String>,
+    /// The kerberos service principal names, such as `nfs/files.example.com`, that tickets
+    /// are requested for when an account begins a session.
+    pub kerberos_services: Vec<String>,
     pub map_group: Vec<GroupMap>,
 }
 
@@ -181,6 +193,7 @@ impl Display for UnixdConfig {
             Some(val) => writeln!(f, "home_alias: {}", val)?,
             None => writeln!(f, "home_alias: unset")?,
         }
+        writeln!(f, "home_skeleton_prefix: {:?}", self.home_skeleton_prefix)?;
 
         writeln!(f, "uid_attr_map: {}", self.uid_attr_map)?;
         writeln!(f, "gid_attr_map: {}", self.gid_attr_map)?;
@@ -197,6 +210,14 @@ impl Display for UnixdConfig {
                 "kanidm pam_allowed_login_groups: {:#?}",
                 kconfig.pam_allowed_login_groups
             )?;
+            if let Some(hbac_host_name) = &kconfig.hbac_host_name {
+                writeln!(f, "kanidm hbac_host_name: {}", hbac_host_name)?;
+            }
+            writeln!(
+                f,
+                "kanidm kerberos_services: {:#?}",
+                kconfig.kerberos_services
+            )?;
             writeln!(f, "kanidm conn_timeout: {}", kconfig.conn_timeout)?;
             writeln!(f, "kanidm request_timeout: {}", kconfig.request_timeout)?;
         } else {
@@ -230,6 +251,7 @@ impl UnixdConfig {
             home_attr: DEFAULT_HOME_ATTR,
             home_alias: DEFAULT_HOME_ALIAS,
             use_etc_skel: DEFAULT_USE_ETC_SKEL,
+            home_skeleton_prefix: DEFAULT_HOME_SKELETON_PREFIX.into(),
             uid_attr_map: DEFAULT_UID_ATTR_MAP,
             gid_attr_map: DEFAULT_GID_ATTR_MAP,
             selinux: DEFAULT_SELINUX,
@@ -314,6 +336,8 @@ impl UnixdConfig {
             conn_timeout: config.conn_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT),
             request_timeout: config.request_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT * 2),
             pam_allowed_login_groups: config.pam_allowed_login_groups.unwrap_or_default(),
+            hbac_host_name: None,
+            kerberos_services: Vec::new(),
             map_group,
         });
 
@@ -356,6 +380,10 @@ impl UnixdConfig {
                 })
                 .unwrap_or(self.home_alias),
             use_etc_skel: config.use_etc_skel.unwrap_or(self.use_etc_skel),
+            home_skeleton_prefix: config
+                .home_skeleton_prefix
+                .map(|p| p.into())
+                .unwrap_or(self.home_skeleton_prefix.clone()),
             uid_attr_map: config
                 .uid_attr_map
                 .and_then(|v| match v.as_str() {
@@ -409,6 +437,8 @@ impl UnixdConfig {
                 conn_timeout: kconfig.conn_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT),
                 request_timeout: kconfig.request_timeout.unwrap_or(DEFAULT_CONN_TIMEOUT * 2),
                 pam_allowed_login_groups: kconfig.pam_allowed_login_groups.unwrap_or_default(),
+                hbac_host_name: kconfig.hbac_host_name,
+                kerberos_services: kconfig.kerberos_services,
                 map_group: kconfig.map_group,
             })
         } else {
@@ -454,6 +484,10 @@ impl UnixdConfig {
                 })
                 .unwrap_or(self.home_alias),
             use_etc_skel: config.use_etc_skel.unwrap_or(self.use_etc_skel),
+            home_skeleton_prefix: config
+                .home_skeleton_prefix
+                .map(|p| p.into())
+                .unwrap_or(self.home_skeleton_prefix.clone()),
             uid_attr_map: config
                 .uid_attr_map
                 .and_then(|v| match v.as_str() {
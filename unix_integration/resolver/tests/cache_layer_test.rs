@@ -134,15 +134,25 @@ async fn setup_test(fix_fn: Fixture) -> (Resolver, KanidmClient) {
             conn_timeout: 1,
             request_timeout: 1,
             pam_allowed_login_groups: vec!["allowed_group".to_string()],
-            map_group: vec![GroupMap {
-                local: "extensible_group".to_string(),
-                with: "testgroup1".to_string(),
-            }],
+            hbac_host_name: None,
+            kerberos_services: Vec::new(),
+            map_group: vec![
+                GroupMap {
+                    local: "extensible_group".to_string(),
+                    with: "testgroup1".to_string(),
+                },
+                GroupMap {
+                    local: "extensible_group".to_string(),
+                    with: "testgroup2".to_string(),
+                },
+            ],
         },
         SystemTime::now(),
         &mut (&mut dbtxn).into(),
         &mut hsm,
         &machine_key,
+        // The soft tpm stands in for a hardware tpm, so that offline authentication is tested.
+        true,
     )
     .unwrap();
 
@@ -557,7 +567,7 @@ async fn test_cache_account_pam_allowed() {
 
     // Should fail
     let a1 = cachelayer
-        .pam_account_allowed("testaccount1")
+        .pam_account_allowed("testaccount1", None)
         .await
         .expect("failed to authenticate");
     assert_eq!(a1, Some(false));
@@ -576,7 +586,7 @@ async fn test_cache_account_pam_allowed() {
 
     // Should pass
     let a2 = cachelayer
-        .pam_account_allowed("testaccount1")
+        .pam_account_allowed("testaccount1", None)
         .await
         .expect("failed to authenticate");
     assert_eq!(a2, Some(true));
@@ -589,7 +599,7 @@ async fn test_cache_account_pam_nonexist() {
     cachelayer.mark_next_check_now(SystemTime::now()).await;
 
     let a1 = cachelayer
-        .pam_account_allowed("NO_SUCH_ACCOUNT")
+        .pam_account_allowed("NO_SUCH_ACCOUNT", None)
         .await
         .expect("failed to authenticate");
     assert!(a1.is_none());
@@ -603,7 +613,7 @@ async fn test_cache_account_pam_nonexist() {
     cachelayer.mark_offline().await;
 
     let a1 = cachelayer
-        .pam_account_allowed("NO_SUCH_ACCOUNT")
+        .pam_account_allowed("NO_SUCH_ACCOUNT", None)
         .await
         .expect("failed to authenticate");
     assert!(a1.is_none());
@@ -656,7 +666,7 @@ async fn test_cache_account_expiry() {
 
     // Pam account allowed should be denied.
     let a3 = cachelayer
-        .pam_account_allowed("testaccount1")
+        .pam_account_allowed("testaccount1", None)
         .await
         .expect("failed to authenticate");
     assert_eq!(a3, Some(false));
@@ -681,7 +691,7 @@ async fn test_cache_account_expiry() {
 
     // Pam account allowed should be denied.
     let a5 = cachelayer
-        .pam_account_allowed("testaccount1")
+        .pam_account_allowed("testaccount1", None)
         .await
         .expect("failed to authenticate");
     assert_eq!(a5, Some(false));
@@ -1023,13 +1033,13 @@ async fn test_cache_authenticate_system_account() {
     // due to how posix auth works, session and authorisation are simpler, and should
     // always just return "true".
     let a1 = cachelayer
-        .pam_account_allowed("testaccount1")
+        .pam_account_allowed("testaccount1", None)
         .await
         .expect("failed to authorise");
     assert_eq!(a1, Some(true));
 
     let a1 = cachelayer
-        .pam_account_allowed("testaccount2")
+        .pam_account_allowed("testaccount2", None)
         .await
         .expect("failed to authorise");
     assert_eq!(a1, Some(true));
@@ -1242,3 +1252,77 @@ async fn test_cache_extend_group_members() {
     assert_eq!(gt.gid, 30001);
     assert_eq!(gt.members.as_slice(), &["local_account".to_string()]);
 }
+
+#[tokio::test]
+/// Test that a local group can be extended by more than one remote group, and that
+/// accounts that are members of many of them are only listed once.
+async fn test_cache_extend_group_members_from_many_groups() {
+    let (cachelayer, adminclient) = setup_test(fixture(test_fixture)).await;
+
+    adminclient
+        .idm_person_account_create("testaccount2", "Posix Demo Account")
+        .await
+        .unwrap();
+    adminclient
+        .idm_person_account_unix_extend("testaccount2", Some(20010), None)
+        .await
+        .unwrap();
+    adminclient
+        .idm_group_create("testgroup2", None)
+        .await
+        .unwrap();
+    adminclient
+        .idm_group_add_members("testgroup2", &["testaccount1", "testaccount2"])
+        .await
+        .unwrap();
+    adminclient
+        .idm_group_unix_extend("testgroup2", Some(20011))
+        .await
+        .unwrap();
+
+    cachelayer
+        .reload_system_identities(
+            vec![],
+            None,
+            vec![EtcGroup {
+                name: "extensible_group".to_string(),
+                gid: 30001,
+                password: Default::default(),
+                members: vec![],
+            }],
+        )
+        .await;
+
+    cachelayer.mark_next_check_now(SystemTime::now()).await;
+    assert!(cachelayer.test_connection().await);
+
+    // Resolve the accounts so that their memberships are linked.
+    for account in ["testaccount1", "testaccount2"] {
+        let ut = cachelayer
+            .get_nssaccount_name(account)
+            .await
+            .expect("Failed to get from cache");
+        assert!(ut.is_some());
+    }
+
+    let expected = [
+        "testaccount1@idm.example.com".to_string(),
+        "testaccount2@idm.example.com".to_string(),
+    ];
+
+    let gt = cachelayer
+        .get_nssgroup_name("extensible_group")
+        .await
+        .expect("Failed to get from cache")
+        .unwrap();
+    assert_eq!(gt.gid, 30001);
+    assert_eq!(gt.members.as_slice(), &expected);
+
+    let groups = cachelayer
+        .get_nssgroups()
+        .await
+        .expect("Failed to get from cache");
+    assert!(groups
+        .iter()
+        .any(|group| group.name == "extensible_group" && group.members.as_slice() == &expected));
+}